@@ -7,4 +7,8 @@ pub trait DnsModifier {
     fn subvert(&self) -> Result<(), String>;
     fn revert(&self) -> Result<(), String>;
     fn inspect(&self, stdout: &mut (dyn io::Write + Send)) -> Result<(), String>;
+    // The service, if any, that rewrites these DNS settings on its own schedule and may undo a subversion
+    fn manager(&self) -> Option<String> {
+        None
+    }
 }
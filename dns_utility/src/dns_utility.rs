@@ -6,6 +6,15 @@ use masq_lib::command::Command;
 use masq_lib::command::StdStreams;
 use std::io::Write;
 
+// Takes what DnsModifier::inspect() writes: one active nameserver per line
+pub fn status_of(dns_server_list: &str) -> &'static str {
+    if dns_server_list == "127.0.0.1\n" {
+        "subverted"
+    } else {
+        "reverted"
+    }
+}
+
 enum Action {
     Subvert,
     Revert,
@@ -93,10 +102,7 @@ impl DnsUtility {
     }
 
     fn status_from_inspect(&self, dns_server_list: String) -> Result<String, String> {
-        match dns_server_list {
-            ref s if s == &String::from("127.0.0.1\n") => Ok(String::from("subverted")),
-            _ => Ok(String::from("reverted")),
-        }
+        Ok(status_of(&dns_server_list).to_string())
     }
 
     fn usage(streams: &mut StdStreams<'_>) -> u8 {
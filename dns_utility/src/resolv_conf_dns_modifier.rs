@@ -2,6 +2,7 @@
 #![cfg(target_os = "linux")]
 use crate::dns_modifier::DnsModifier;
 use regex::Regex;
+use std::fs;
 use std::fs::File;
 use std::fs::OpenOptions;
 use std::io;
@@ -14,6 +15,10 @@ use std::ops::Add;
 use std::path::Path;
 use std::path::PathBuf;
 
+// Both leave their names in the comments of the resolv.conf they write
+const SYSTEMD_RESOLVED: &str = "systemd-resolved";
+const NETWORK_MANAGER: &str = "NetworkManager";
+
 pub struct ResolvConfDnsModifier {
     root: PathBuf,
 }
@@ -42,6 +47,23 @@ impl DnsModifier for ResolvConfDnsModifier {
         let (_, contents) = self.open_resolv_conf(false)?;
         self.inspect_contents(contents, stdout)
     }
+
+    fn manager(&self) -> Option<String> {
+        let path = self.resolv_conf_path();
+        if let Ok(target) = fs::read_link(&path) {
+            if target.to_string_lossy().contains("systemd/resolve") {
+                return Some(SYSTEMD_RESOLVED.to_string());
+            }
+        }
+        let contents = fs::read_to_string(&path).ok()?;
+        if contents.contains(SYSTEMD_RESOLVED) {
+            Some(SYSTEMD_RESOLVED.to_string())
+        } else if contents.contains(NETWORK_MANAGER) {
+            Some(NETWORK_MANAGER.to_string())
+        } else {
+            None
+        }
+    }
 }
 
 impl Default for ResolvConfDnsModifier {
@@ -62,9 +84,7 @@ impl ResolvConfDnsModifier {
         open_options.read(true);
         open_options.write(for_write);
         open_options.create(false);
-        let path = Path::new(&self.root)
-            .join(Path::new("etc"))
-            .join(Path::new("resolv.conf"));
+        let path = self.resolv_conf_path();
         let mut file = match open_options.open(path.clone()) {
             Ok(f) => f,
             Err(ref e) if e.kind() == ErrorKind::NotFound => {
@@ -96,6 +116,12 @@ impl ResolvConfDnsModifier {
         Ok((file, contents))
     }
 
+    fn resolv_conf_path(&self) -> PathBuf {
+        Path::new(&self.root)
+            .join(Path::new("etc"))
+            .join(Path::new("resolv.conf"))
+    }
+
     fn process_msg(msg: &str, for_write: bool) -> String {
         if for_write {
             format!("{} and could not be modified", msg)
@@ -838,6 +864,53 @@ mod tests {
         assert_eq!(result.is_ok(), true);
     }
 
+    #[test]
+    fn manager_recognizes_a_resolv_conf_linked_to_systemd_resolved() {
+        let root = make_root("manager_recognizes_a_resolv_conf_linked_to_systemd_resolved");
+        let stub_dir = root.join("run").join("systemd").join("resolve");
+        fs::create_dir_all(&stub_dir).unwrap();
+        fs::write(stub_dir.join("stub-resolv.conf"), "nameserver 127.0.0.53\n").unwrap();
+        fs::create_dir_all(root.join("etc")).unwrap();
+        std::os::unix::fs::symlink(
+            stub_dir.join("stub-resolv.conf"),
+            root.join("etc").join("resolv.conf"),
+        )
+        .unwrap();
+        let mut subject = ResolvConfDnsModifier::new();
+        subject.root = root;
+
+        let result = subject.manager();
+
+        assert_eq!(result, Some("systemd-resolved".to_string()));
+    }
+
+    #[test]
+    fn manager_recognizes_a_resolv_conf_written_by_network_manager() {
+        let root = make_root("manager_recognizes_a_resolv_conf_written_by_network_manager");
+        make_resolv_conf(
+            &root,
+            "# Generated by NetworkManager\nsearch home\nnameserver 192.168.0.1\n",
+        );
+        let mut subject = ResolvConfDnsModifier::new();
+        subject.root = root;
+
+        let result = subject.manager();
+
+        assert_eq!(result, Some("NetworkManager".to_string()));
+    }
+
+    #[test]
+    fn manager_finds_nobody_managing_a_hand_written_resolv_conf() {
+        let root = make_root("manager_finds_nobody_managing_a_hand_written_resolv_conf");
+        make_resolv_conf(&root, "#comment\nnameserver 8.8.8.8\n");
+        let mut subject = ResolvConfDnsModifier::new();
+        subject.root = root;
+
+        let result = subject.manager();
+
+        assert_eq!(result, None);
+    }
+
     fn make_root(test_name: &str) -> PathBuf {
         let cur_dir = env::current_dir().unwrap();
         let generated_dir = cur_dir.join(Path::new("generated"));
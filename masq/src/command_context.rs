@@ -4,9 +4,10 @@ use crate::command_context::ContextError::ConnectionRefused;
 use crate::communications::broadcast_handler::{
     BroadcastHandler, BroadcastHandlerReal, StreamFactory,
 };
+use crate::communications::client_listener_thread::ClientListenerError;
 use crate::communications::connection_manager::{ConnectionManager, REDIRECT_TIMEOUT_MILLIS};
 use crate::communications::node_conversation::ClientError;
-use masq_lib::messages::{TIMEOUT_ERROR, UNMARSHAL_ERROR};
+use masq_lib::messages::{AUTHENTICATION_ERROR, TIMEOUT_ERROR, UNMARSHAL_ERROR};
 use masq_lib::ui_gateway::MessageBody;
 use std::fmt::{Debug, Formatter};
 use std::io;
@@ -123,18 +124,27 @@ impl CommandContext for CommandContextReal {
 impl CommandContextReal {
     pub fn new(
         daemon_ui_port: u16,
+        ui_password_opt: Option<String>,
         broadcast_stream_factory: Box<dyn StreamFactory>,
     ) -> Result<Self, ContextError> {
         let mut connection = ConnectionManager::new();
         let broadcast_handler = BroadcastHandlerReal::new();
         let broadcast_handle = broadcast_handler.start(broadcast_stream_factory);
-        match connection.connect(daemon_ui_port, broadcast_handle, REDIRECT_TIMEOUT_MILLIS) {
+        match connection.connect(
+            daemon_ui_port,
+            ui_password_opt,
+            broadcast_handle,
+            REDIRECT_TIMEOUT_MILLIS,
+        ) {
             Ok(_) => Ok(Self {
                 connection,
                 stdin: Box::new(io::stdin()),
                 stdout: Box::new(io::stdout()),
                 stderr: Box::new(io::stderr()),
             }),
+            Err(ClientListenerError::Unauthorized(message)) => {
+                Err(ContextError::PayloadError(AUTHENTICATION_ERROR, message))
+            }
             Err(e) => Err(ConnectionRefused(format!("{:?}", e))),
         }
     }
@@ -150,7 +160,10 @@ mod tests {
     use masq_lib::messages::{
         FromMessageBody, UiCrashRequest, UiSetupRequest, TIMEOUT_ERROR, UNMARSHAL_ERROR,
     };
-    use masq_lib::messages::{ToMessageBody, UiShutdownRequest, UiShutdownResponse};
+    use masq_lib::messages::{
+        ToMessageBody, UiAuthenticateRequest, UiAuthenticateResponse, UiShutdownRequest,
+        UiShutdownResponse,
+    };
     use masq_lib::test_utils::fake_stream_holder::{ByteArrayReader, ByteArrayWriter};
     use masq_lib::test_utils::mock_websockets_server::MockWebSocketsServer;
     use masq_lib::ui_gateway::MessageBody;
@@ -206,7 +219,8 @@ mod tests {
         let server = MockWebSocketsServer::new(port);
         let handle = server.start();
 
-        let subject = CommandContextReal::new(port, Box::new(StreamFactoryReal::new())).unwrap();
+        let subject =
+            CommandContextReal::new(port, None, Box::new(StreamFactoryReal::new())).unwrap();
 
         assert_eq!(subject.active_port(), Some(port));
         handle.stop();
@@ -224,7 +238,7 @@ mod tests {
         let server = MockWebSocketsServer::new(port).queue_response(UiShutdownResponse {}.tmb(1));
         let stop_handle = server.start();
         let mut subject =
-            CommandContextReal::new(port, Box::new(StreamFactoryReal::new())).unwrap();
+            CommandContextReal::new(port, None, Box::new(StreamFactoryReal::new())).unwrap();
         subject.stdin = Box::new(stdin);
         subject.stdout = Box::new(stdout);
         subject.stderr = Box::new(stderr);
@@ -256,7 +270,7 @@ mod tests {
         running_test();
         let port = find_free_port();
 
-        let result = CommandContextReal::new(port, Box::new(StreamFactoryReal::new()));
+        let result = CommandContextReal::new(port, None, Box::new(StreamFactoryReal::new()));
 
         match result {
             Err(ConnectionRefused(_)) => (),
@@ -265,6 +279,81 @@ mod tests {
         }
     }
 
+    #[test]
+    fn authenticates_with_the_ui_password_before_anything_else() {
+        running_test();
+        let port = find_free_port();
+        let server = MockWebSocketsServer::new(port)
+            .queue_response(
+                UiAuthenticateResponse {
+                    permission: "full".to_string(),
+                }
+                .tmb(0),
+            )
+            .queue_response(UiShutdownResponse {}.tmb(1));
+        let stop_handle = server.start();
+        let mut subject = CommandContextReal::new(
+            port,
+            Some("booga".to_string()),
+            Box::new(StreamFactoryReal::new()),
+        )
+        .unwrap();
+
+        let response = subject.transact(UiShutdownRequest {}.tmb(1), 1000).unwrap();
+
+        assert_eq!(
+            UiShutdownResponse::fmb(response).unwrap(),
+            (UiShutdownResponse {}, 1)
+        );
+        let requests = stop_handle.stop();
+        assert_eq!(
+            UiAuthenticateRequest::fmb(requests[0].clone().unwrap()).unwrap(),
+            (
+                UiAuthenticateRequest {
+                    password: "booga".to_string()
+                },
+                0
+            )
+        );
+        assert_eq!(
+            UiShutdownRequest::fmb(requests[1].clone().unwrap()).unwrap(),
+            (UiShutdownRequest {}, 1)
+        );
+    }
+
+    #[test]
+    fn reports_a_ui_password_that_isnt_accepted() {
+        running_test();
+        let port = find_free_port();
+        let server = MockWebSocketsServer::new(port).queue_response(MessageBody {
+            opcode: "authenticate".to_string(),
+            path: Conversation(0),
+            payload: Err((
+                AUTHENTICATION_ERROR,
+                "Wrong password; 4 more tries before 127.0.0.1 is locked out".to_string(),
+            )),
+        });
+        let stop_handle = server.start();
+
+        let result = CommandContextReal::new(
+            port,
+            Some("booga".to_string()),
+            Box::new(StreamFactoryReal::new()),
+        );
+
+        match result {
+            Err(e) => assert_eq!(
+                e,
+                PayloadError(
+                    AUTHENTICATION_ERROR,
+                    "Wrong password; 4 more tries before 127.0.0.1 is locked out".to_string()
+                )
+            ),
+            Ok(_) => panic!("Succeeded when it should have failed"),
+        }
+        stop_handle.stop();
+    }
+
     #[test]
     fn transact_works_when_server_sends_payload_error() {
         running_test();
@@ -276,9 +365,16 @@ mod tests {
         });
         let stop_handle = server.start();
         let mut subject =
-            CommandContextReal::new(port, Box::new(StreamFactoryReal::new())).unwrap();
-
-        let response = subject.transact(UiSetupRequest { values: vec![] }.tmb(1), 1000);
+            CommandContextReal::new(port, None, Box::new(StreamFactoryReal::new())).unwrap();
+
+        let response = subject.transact(
+            UiSetupRequest {
+                values: vec![],
+                instance_opt: None,
+            }
+            .tmb(1),
+            1000,
+        );
 
         assert_eq!(response, Err(PayloadError(101, "booga".to_string())));
         stop_handle.stop();
@@ -291,9 +387,16 @@ mod tests {
         let server = MockWebSocketsServer::new(port).queue_string("disconnect");
         let stop_handle = server.start();
         let mut subject =
-            CommandContextReal::new(port, Box::new(StreamFactoryReal::new())).unwrap();
-
-        let response = subject.transact(UiSetupRequest { values: vec![] }.tmb(1), 1000);
+            CommandContextReal::new(port, None, Box::new(StreamFactoryReal::new())).unwrap();
+
+        let response = subject.transact(
+            UiSetupRequest {
+                values: vec![],
+                instance_opt: None,
+            }
+            .tmb(1),
+            1000,
+        );
 
         match response {
             Err(ConnectionDropped(_)) => (),
@@ -314,7 +417,7 @@ mod tests {
         let server = MockWebSocketsServer::new(port);
         let stop_handle = server.start();
         let stream_factory = Box::new(StreamFactoryReal::new());
-        let subject_result = CommandContextReal::new(port, stream_factory);
+        let subject_result = CommandContextReal::new(port, None, stream_factory);
         let mut subject = subject_result.unwrap();
         subject.stdin = Box::new(stdin);
         subject.stdout = Box::new(stdout);
@@ -1,12 +1,19 @@
 // Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
 
 use crate::command_factory::CommandFactoryError::{CommandSyntax, UnrecognizedSubcommand};
+use crate::commands::application_rules_command::ApplicationRulesCommand;
 use crate::commands::commands_common::Command;
 use crate::commands::crash_command::CrashCommand;
 use crate::commands::descriptor_command::DescriptorCommand;
+use crate::commands::encrypted_sni_host_command::EncryptedSniHostCommand;
+use crate::commands::neighborhood_graph_command::NeighborhoodGraphCommand;
 use crate::commands::setup_command::SetupCommand;
 use crate::commands::shutdown_command::ShutdownCommand;
+use crate::commands::speed_test_command::SpeedTestCommand;
+use crate::commands::split_tunnel_rules_command::SplitTunnelRulesCommand;
 use crate::commands::start_command::StartCommand;
+use crate::commands::streams_command::StreamsCommand;
+use crate::commands::tui_command::TuiCommand;
 
 #[derive(Debug, PartialEq)]
 pub enum CommandFactoryError {
@@ -24,17 +31,42 @@ pub struct CommandFactoryReal {}
 impl CommandFactory for CommandFactoryReal {
     fn make(&self, pieces: Vec<String>) -> Result<Box<dyn Command>, CommandFactoryError> {
         let boxed_command: Box<dyn Command> = match pieces[0].as_str() {
+            "application-rules" => match ApplicationRulesCommand::new(&pieces[..]) {
+                Ok(command) => Box::new(command),
+                Err(msg) => return Err(CommandSyntax(msg)),
+            },
             "crash" => match CrashCommand::new(&pieces[..]) {
                 Ok(command) => Box::new(command),
                 Err(msg) => return Err(CommandSyntax(msg)),
             },
-            "descriptor" => Box::new(DescriptorCommand::new()),
+            "descriptor" => match DescriptorCommand::new(&pieces[..]) {
+                Ok(command) => Box::new(command),
+                Err(msg) => return Err(CommandSyntax(msg)),
+            },
+            "encrypted-sni-host" => match EncryptedSniHostCommand::new(&pieces[..]) {
+                Ok(command) => Box::new(command),
+                Err(msg) => return Err(CommandSyntax(msg)),
+            },
+            "neighborhood-graph" => Box::new(NeighborhoodGraphCommand::new()),
             "setup" => match SetupCommand::new(pieces) {
                 Ok(command) => Box::new(command),
                 Err(msg) => return Err(CommandSyntax(msg)),
             },
             "shutdown" => Box::new(ShutdownCommand::new()),
+            "speed-test" => match SpeedTestCommand::new(&pieces[..]) {
+                Ok(command) => Box::new(command),
+                Err(msg) => return Err(CommandSyntax(msg)),
+            },
+            "split-tunnel-rules" => match SplitTunnelRulesCommand::new(&pieces[..]) {
+                Ok(command) => Box::new(command),
+                Err(msg) => return Err(CommandSyntax(msg)),
+            },
             "start" => Box::new(StartCommand::new()),
+            "streams" => Box::new(StreamsCommand::new()),
+            "tui" => match TuiCommand::new(&pieces[..]) {
+                Ok(command) => Box::new(command),
+                Err(msg) => return Err(CommandSyntax(msg)),
+            },
             unrecognized => return Err(UnrecognizedSubcommand(unrecognized.to_string())),
         };
         Ok(boxed_command)
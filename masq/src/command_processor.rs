@@ -26,9 +26,11 @@ impl CommandProcessorFactory for CommandProcessorFactoryReal {
     ) -> Result<Box<dyn CommandProcessor>, CommandError> {
         let matches = app().get_matches_from(args);
         let ui_port = value_t!(matches, "ui-port", u16).expect("ui-port is not properly defaulted");
-        match CommandContextReal::new(ui_port, broadcast_stream_factory) {
+        let ui_password_opt = matches.value_of("ui-password").map(|s| s.to_string());
+        match CommandContextReal::new(ui_port, ui_password_opt, broadcast_stream_factory) {
             Ok(context) => Ok(Box::new(CommandProcessorReal { context })),
             Err(ContextError::ConnectionRefused(s)) => Err(CommandError::ConnectionProblem(s)),
+            Err(ContextError::PayloadError(code, s)) => Err(CommandError::Payload(code, s)),
             Err(e) => panic!("Unexpected error: {:?}", e),
         }
     }
@@ -66,7 +68,9 @@ mod tests {
     use crate::command_context::CommandContext;
     use crate::communications::broadcast_handler::StreamFactoryReal;
     use masq_lib::messages::ToMessageBody;
-    use masq_lib::messages::{UiShutdownRequest, UiShutdownResponse};
+    use masq_lib::messages::{
+        UiAuthenticateRequest, UiAuthenticateResponse, UiShutdownRequest, UiShutdownResponse,
+    };
     use masq_lib::test_utils::mock_websockets_server::MockWebSocketsServer;
     use masq_lib::utils::find_free_port;
 
@@ -124,4 +128,44 @@ mod tests {
         let received = stop_handle.stop();
         assert_eq!(received, vec![Ok(UiShutdownRequest {}.tmb(1))]);
     }
+
+    #[test]
+    fn factory_parses_out_the_ui_password_when_specified() {
+        let port = find_free_port();
+        let args = [
+            "masq".to_string(),
+            "--ui-port".to_string(),
+            format!("{}", port),
+            "--ui-password".to_string(),
+            "booga".to_string(),
+        ];
+        let subject = CommandProcessorFactoryReal::new();
+        let server = MockWebSocketsServer::new(port)
+            .queue_response(
+                UiAuthenticateResponse {
+                    permission: "full".to_string(),
+                }
+                .tmb(0),
+            )
+            .queue_response(UiShutdownResponse {}.tmb(1));
+        let stop_handle = server.start();
+
+        let mut result = subject
+            .make(Box::new(StreamFactoryReal::new()), &args)
+            .unwrap();
+
+        let command = TestCommand {};
+        result.process(Box::new(command)).unwrap();
+        let received = stop_handle.stop();
+        assert_eq!(
+            received,
+            vec![
+                Ok(UiAuthenticateRequest {
+                    password: "booga".to_string()
+                }
+                .tmb(0)),
+                Ok(UiShutdownRequest {}.tmb(1))
+            ]
+        );
+    }
 }
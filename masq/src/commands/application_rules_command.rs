@@ -0,0 +1,324 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+
+use crate::command_context::CommandContext;
+use crate::commands::commands_common::CommandError::Payload;
+use crate::commands::commands_common::{
+    transaction, Command, CommandError, STANDARD_COMMAND_TIMEOUT_MILLIS,
+};
+use clap::{App, Arg, ArgGroup, SubCommand};
+use masq_lib::messages::{
+    UiApplicationRulesRequest, UiApplicationRulesResponse, UiSetApplicationRulesRequest,
+    UiSetApplicationRulesResponse, APPLICATION_RULES_ERROR, NODE_NOT_RUNNING_ERROR,
+};
+use std::fmt::Debug;
+
+#[derive(Debug, PartialEq)]
+pub struct ApplicationRulesCommand {
+    rules_opt: Option<Vec<String>>,
+}
+
+pub fn application_rules_subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("application-rules")
+        .about("Displays or changes the application rules of the running MASQNode. Each rule decides where the connections \
+        of one program on this computer go, whatever the split-tunnel rules say; the connections of other programs are left \
+        to the split-tunnel rules. Only valid if Node is already running.")
+        .arg(Arg::with_name("set")
+            .help("Replaces the current rules with these. Each rule is <application>:<action>, where the application is \
+            the name of a program without its path, such as firefox, and the action is masq (through MASQ), direct (straight \
+            to its destination), or block (not at all). The first rule that matches a program decides")
+            .long("set")
+            .value_name("RULE")
+            .takes_value(true)
+            .multiple(true)
+            .use_delimiter(true))
+        .arg(Arg::with_name("clear")
+            .help("Removes all the rules, so that only the split-tunnel rules decide")
+            .long("clear")
+            .takes_value(false))
+        .group(ArgGroup::with_name("change").args(&["set", "clear"]))
+}
+
+impl Command for ApplicationRulesCommand {
+    fn execute(&self, context: &mut dyn CommandContext) -> Result<(), CommandError> {
+        let result = match &self.rules_opt {
+            None => Self::display_rules(context),
+            Some(rules) => Self::change_rules(context, rules.clone()),
+        };
+        match result {
+            Ok(()) => Ok(()),
+            Err(Payload(code, message)) if code == NODE_NOT_RUNNING_ERROR => {
+                writeln!(
+                    context.stderr(),
+                    "MASQNode is not running; therefore its application rules cannot be accessed."
+                )
+                .expect("write! failed");
+                Err(Payload(code, message))
+            }
+            Err(Payload(code, message)) if code == APPLICATION_RULES_ERROR => {
+                writeln!(context.stderr(), "{}", message).expect("write! failed");
+                Err(Payload(code, message))
+            }
+            Err(e) => {
+                writeln!(
+                    context.stderr(),
+                    "Application rules request failed: {:?}",
+                    e
+                )
+                .expect("write! failed");
+                Err(e)
+            }
+        }
+    }
+}
+
+impl ApplicationRulesCommand {
+    pub fn new(pieces: &[String]) -> Result<Self, String> {
+        let matches = match application_rules_subcommand().get_matches_from_safe(pieces) {
+            Ok(matches) => matches,
+            Err(e) => return Err(format!("{}", e)),
+        };
+        let rules_opt = if matches.is_present("clear") {
+            Some(vec![])
+        } else {
+            matches
+                .values_of("set")
+                .map(|values| values.map(|value| value.to_string()).collect())
+        };
+        Ok(Self { rules_opt })
+    }
+
+    fn display_rules(context: &mut dyn CommandContext) -> Result<(), CommandError> {
+        let response: UiApplicationRulesResponse = transaction(
+            UiApplicationRulesRequest {},
+            context,
+            STANDARD_COMMAND_TIMEOUT_MILLIS,
+        )?;
+        if response.rules.is_empty() {
+            writeln!(
+                context.stdout(),
+                "No application rules: the split-tunnel rules decide."
+            )
+            .expect("write! failed");
+        } else {
+            writeln!(context.stdout(), "Application rules, first match first:")
+                .expect("write! failed");
+            response.rules.iter().for_each(|rule| {
+                writeln!(context.stdout(), "    {}", rule).expect("write! failed")
+            });
+        }
+        Ok(())
+    }
+
+    fn change_rules(
+        context: &mut dyn CommandContext,
+        rules: Vec<String>,
+    ) -> Result<(), CommandError> {
+        let _: UiSetApplicationRulesResponse = transaction(
+            UiSetApplicationRulesRequest { rules },
+            context,
+            STANDARD_COMMAND_TIMEOUT_MILLIS,
+        )?;
+        writeln!(context.stdout(), "Application rules changed.").expect("write! failed");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_context::ContextError;
+    use crate::command_context::ContextError::ConnectionDropped;
+    use crate::command_factory::{CommandFactory, CommandFactoryReal};
+    use crate::commands::commands_common::CommandError::ConnectionProblem;
+    use crate::test_utils::mocks::CommandContextMock;
+    use masq_lib::messages::ToMessageBody;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn testing_command_factory_here() {
+        let factory = CommandFactoryReal::new();
+        let mut context = CommandContextMock::new()
+            .transact_result(Ok(UiApplicationRulesResponse { rules: vec![] }.tmb(0)));
+        let subject = factory.make(vec!["application-rules".to_string()]).unwrap();
+
+        let result = subject.execute(&mut context);
+
+        assert_eq!(result, Ok(()));
+    }
+
+    #[test]
+    fn command_line_is_interpreted_properly() {
+        let make = |args: &[&str]| {
+            ApplicationRulesCommand::new(
+                &args
+                    .iter()
+                    .map(|arg| arg.to_string())
+                    .collect::<Vec<String>>(),
+            )
+        };
+
+        assert_eq!(
+            make(&["application-rules"]),
+            Ok(ApplicationRulesCommand { rules_opt: None })
+        );
+        assert_eq!(
+            make(&[
+                "application-rules",
+                "--set",
+                "firefox:masq,updater:direct,curl:block"
+            ]),
+            Ok(ApplicationRulesCommand {
+                rules_opt: Some(vec![
+                    "firefox:masq".to_string(),
+                    "updater:direct".to_string(),
+                    "curl:block".to_string()
+                ])
+            })
+        );
+        assert_eq!(
+            make(&["application-rules", "--clear"]),
+            Ok(ApplicationRulesCommand {
+                rules_opt: Some(vec![])
+            })
+        );
+        assert_eq!(
+            make(&["application-rules", "--clear", "--set", "curl:block"]).is_err(),
+            true
+        );
+    }
+
+    #[test]
+    fn displays_rules_when_there_are_some() {
+        let transact_params_arc = Arc::new(Mutex::new(vec![]));
+        let mut context = CommandContextMock::new()
+            .transact_params(&transact_params_arc)
+            .transact_result(Ok(UiApplicationRulesResponse {
+                rules: vec!["firefox:masq".to_string(), "curl:block".to_string()],
+            }
+            .tmb(0)));
+        let stdout_arc = context.stdout_arc();
+        let stderr_arc = context.stderr_arc();
+        let subject = ApplicationRulesCommand { rules_opt: None };
+
+        let result = subject.execute(&mut context);
+
+        assert_eq!(result, Ok(()));
+        let transact_params = transact_params_arc.lock().unwrap();
+        assert_eq!(
+            *transact_params,
+            vec![(
+                UiApplicationRulesRequest {}.tmb(0),
+                STANDARD_COMMAND_TIMEOUT_MILLIS
+            )]
+        );
+        assert_eq!(
+            stdout_arc.lock().unwrap().get_string(),
+            "Application rules, first match first:\n    firefox:masq\n    curl:block\n"
+        );
+        assert_eq!(stderr_arc.lock().unwrap().get_string(), String::new());
+    }
+
+    #[test]
+    fn displays_absence_of_rules() {
+        let mut context = CommandContextMock::new()
+            .transact_result(Ok(UiApplicationRulesResponse { rules: vec![] }.tmb(0)));
+        let stdout_arc = context.stdout_arc();
+        let subject = ApplicationRulesCommand { rules_opt: None };
+
+        let result = subject.execute(&mut context);
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(
+            stdout_arc.lock().unwrap().get_string(),
+            "No application rules: the split-tunnel rules decide.\n"
+        );
+    }
+
+    #[test]
+    fn changes_rules() {
+        let transact_params_arc = Arc::new(Mutex::new(vec![]));
+        let mut context = CommandContextMock::new()
+            .transact_params(&transact_params_arc)
+            .transact_result(Ok(UiSetApplicationRulesResponse {}.tmb(0)));
+        let stdout_arc = context.stdout_arc();
+        let subject = ApplicationRulesCommand {
+            rules_opt: Some(vec!["curl:block".to_string()]),
+        };
+
+        let result = subject.execute(&mut context);
+
+        assert_eq!(result, Ok(()));
+        let transact_params = transact_params_arc.lock().unwrap();
+        assert_eq!(
+            *transact_params,
+            vec![(
+                UiSetApplicationRulesRequest {
+                    rules: vec!["curl:block".to_string()]
+                }
+                .tmb(0),
+                STANDARD_COMMAND_TIMEOUT_MILLIS
+            )]
+        );
+        assert_eq!(
+            stdout_arc.lock().unwrap().get_string(),
+            "Application rules changed.\n"
+        );
+    }
+
+    #[test]
+    fn reports_rejected_rules() {
+        let mut context = CommandContextMock::new().transact_result(Err(
+            ContextError::PayloadError(APPLICATION_RULES_ERROR, "Bad rule".to_string()),
+        ));
+        let stdout_arc = context.stdout_arc();
+        let stderr_arc = context.stderr_arc();
+        let subject = ApplicationRulesCommand {
+            rules_opt: Some(vec!["curl:booga".to_string()]),
+        };
+
+        let result = subject.execute(&mut context);
+
+        assert_eq!(
+            result,
+            Err(Payload(APPLICATION_RULES_ERROR, "Bad rule".to_string()))
+        );
+        assert_eq!(stdout_arc.lock().unwrap().get_string(), String::new());
+        assert_eq!(stderr_arc.lock().unwrap().get_string(), "Bad rule\n");
+    }
+
+    #[test]
+    fn doesnt_work_if_node_is_not_running() {
+        let mut context = CommandContextMock::new().transact_result(Err(
+            ContextError::PayloadError(NODE_NOT_RUNNING_ERROR, "irrelevant".to_string()),
+        ));
+        let stderr_arc = context.stderr_arc();
+        let subject = ApplicationRulesCommand { rules_opt: None };
+
+        let result = subject.execute(&mut context);
+
+        assert_eq!(
+            result,
+            Err(Payload(NODE_NOT_RUNNING_ERROR, "irrelevant".to_string()))
+        );
+        assert_eq!(
+            stderr_arc.lock().unwrap().get_string(),
+            "MASQNode is not running; therefore its application rules cannot be accessed.\n"
+        );
+    }
+
+    #[test]
+    fn application_rules_command_sad_path() {
+        let mut context =
+            CommandContextMock::new().transact_result(Err(ConnectionDropped("Booga".to_string())));
+        let stderr_arc = context.stderr_arc();
+        let subject = ApplicationRulesCommand { rules_opt: None };
+
+        let result = subject.execute(&mut context);
+
+        assert_eq!(result, Err(ConnectionProblem("Booga".to_string())));
+        assert_eq!(
+            stderr_arc.lock().unwrap().get_string(),
+            "Application rules request failed: ConnectionProblem(\"Booga\")\n"
+        );
+    }
+}
@@ -107,7 +107,7 @@ mod tests {
             .transact_result(Err(ContextError::ConnectionDropped("booga".to_string())));
 
         let result: Result<UiStartResponse, CommandError> =
-            transaction(UiStartOrder {}, &mut context, 1000);
+            transaction(UiStartOrder { instance_opt: None }, &mut context, 1000);
 
         assert_eq!(result, Err(ConnectionProblem("booga".to_string())));
     }
@@ -118,7 +118,7 @@ mod tests {
             .transact_result(Err(ContextError::PayloadError(10, "booga".to_string())));
 
         let result: Result<UiStartResponse, CommandError> =
-            transaction(UiStartOrder {}, &mut context, 1000);
+            transaction(UiStartOrder { instance_opt: None }, &mut context, 1000);
 
         assert_eq!(result, Err(Payload(10, "booga".to_string())));
     }
@@ -131,7 +131,7 @@ mod tests {
         let stderr_arc = context.stderr_arc();
 
         let result: Result<UiStartResponse, CommandError> =
-            transaction(UiStartOrder {}, &mut context, 1000);
+            transaction(UiStartOrder { instance_opt: None }, &mut context, 1000);
 
         assert_eq!(result, Err(Transmission("booga".to_string())));
         assert_eq!(stdout_arc.lock().unwrap().get_string(), String::new());
@@ -149,7 +149,7 @@ mod tests {
         let stderr_arc = context.stderr_arc();
 
         let result: Result<UiStartResponse, CommandError> =
-            transaction(UiStartOrder {}, &mut context, 1000);
+            transaction(UiStartOrder { instance_opt: None }, &mut context, 1000);
 
         assert_eq!(
             result,
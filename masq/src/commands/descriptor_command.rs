@@ -5,16 +5,37 @@ use crate::commands::commands_common::CommandError::Payload;
 use crate::commands::commands_common::{
     transaction, Command, CommandError, STANDARD_COMMAND_TIMEOUT_MILLIS,
 };
+#[cfg(feature = "clipboard")]
+use clap::Arg;
 use clap::{App, SubCommand};
+#[cfg(feature = "clipboard")]
+use masq_lib::clipboard::{copy_for_a_while, Clipboard, SystemClipboard, CLIPBOARD_TIMEOUT};
 use masq_lib::messages::{UiDescriptorRequest, UiDescriptorResponse, NODE_NOT_RUNNING_ERROR};
 use std::fmt::Debug;
+#[cfg(feature = "clipboard")]
+use std::sync::Arc;
+#[cfg(feature = "clipboard")]
+use std::time::Duration;
 
 #[derive(Debug)]
-pub struct DescriptorCommand {}
+pub struct DescriptorCommand {
+    #[cfg(feature = "clipboard")]
+    clipboard_opt: Option<Arc<dyn Clipboard>>,
+    #[cfg(feature = "clipboard")]
+    clipboard_timeout: Duration,
+}
 
 pub fn descriptor_subcommand() -> App<'static, 'static> {
-    SubCommand::with_name("descriptor")
-        .about("Displays the Node descriptor of the running MASQNode. Only valid if Node is already running.")
+    let subcommand = SubCommand::with_name("descriptor")
+        .about("Displays the Node descriptor of the running MASQNode. Only valid if Node is already running.");
+    #[cfg(feature = "clipboard")]
+    let subcommand = subcommand.arg(
+        Arg::with_name("copy-to-clipboard")
+            .long("copy-to-clipboard")
+            .takes_value(false)
+            .help("Also puts the descriptor on the clipboard, and clears it again after 30 seconds. masq doesn't exit until it has."),
+    );
+    subcommand
 }
 
 impl Command for DescriptorCommand {
@@ -25,6 +46,8 @@ impl Command for DescriptorCommand {
         match output {
             Ok(response) => {
                 writeln!(context.stdout(), "{}", response.node_descriptor).expect("write! failed");
+                #[cfg(feature = "clipboard")]
+                self.copy_descriptor(context, &response.node_descriptor);
                 Ok(())
             }
             Err(Payload(code, message)) if code == NODE_NOT_RUNNING_ERROR => {
@@ -44,15 +67,46 @@ impl Command for DescriptorCommand {
     }
 }
 
-impl Default for DescriptorCommand {
-    fn default() -> Self {
-        Self::new()
+impl DescriptorCommand {
+    pub fn new(pieces: &[String]) -> Result<Self, String> {
+        let matches = match descriptor_subcommand().get_matches_from_safe(pieces) {
+            Ok(matches) => matches,
+            Err(e) => return Err(format!("{}", e)),
+        };
+        #[cfg(not(feature = "clipboard"))]
+        let _ = matches;
+        Ok(Self {
+            #[cfg(feature = "clipboard")]
+            clipboard_opt: if matches.is_present("copy-to-clipboard") {
+                Some(Arc::new(SystemClipboard {}))
+            } else {
+                None
+            },
+            #[cfg(feature = "clipboard")]
+            clipboard_timeout: CLIPBOARD_TIMEOUT,
+        })
     }
-}
 
-impl DescriptorCommand {
-    pub fn new() -> Self {
-        Self {}
+    // masq waits for the clipboard to be cleared before it exits; in interactive mode, that's
+    // usually long after
+    #[cfg(feature = "clipboard")]
+    fn copy_descriptor(&self, context: &mut dyn CommandContext, descriptor: &str) {
+        if let Some(clipboard) = &self.clipboard_opt {
+            match copy_for_a_while(clipboard.clone(), descriptor, self.clipboard_timeout) {
+                Ok(()) => writeln!(
+                    context.stdout(),
+                    "Copied to the clipboard; clearing it in {} seconds",
+                    self.clipboard_timeout.as_secs()
+                )
+                .expect("write! failed"),
+                Err(e) => writeln!(
+                    context.stderr(),
+                    "Couldn't copy the descriptor to the clipboard: {}",
+                    e
+                )
+                .expect("write! failed"),
+            }
+        }
     }
 }
 
@@ -89,7 +143,7 @@ mod tests {
         ));
         let stdout_arc = context.stdout_arc();
         let stderr_arc = context.stderr_arc();
-        let subject = DescriptorCommand::new();
+        let subject = DescriptorCommand::new(&["descriptor".to_string()]).unwrap();
 
         let result = subject.execute(&mut context);
 
@@ -118,7 +172,7 @@ mod tests {
             .transact_result(Ok(expected_response.tmb(42)));
         let stdout_arc = context.stdout_arc();
         let stderr_arc = context.stderr_arc();
-        let subject = DescriptorCommand::new();
+        let subject = DescriptorCommand::new(&["descriptor".to_string()]).unwrap();
 
         let result = subject.execute(&mut context);
 
@@ -135,6 +189,38 @@ mod tests {
         assert_eq!(stderr_arc.lock().unwrap().get_string(), String::new());
     }
 
+    #[cfg(feature = "clipboard")]
+    #[test]
+    fn descriptor_command_copies_the_descriptor_to_the_clipboard_when_asked() {
+        use masq_lib::clipboard::wait_for_clipboard_clears;
+        use masq_lib::test_utils::clipboard_mock::ClipboardMock;
+        let mut context = CommandContextMock::new().transact_result(Ok(UiDescriptorResponse {
+            node_descriptor: "Booga:1234".to_string(),
+        }
+        .tmb(42)));
+        let stdout_arc = context.stdout_arc();
+        let clipboard = Arc::new(ClipboardMock::default());
+        let mut subject =
+            DescriptorCommand::new(&["descriptor".to_string(), "--copy-to-clipboard".to_string()])
+                .unwrap();
+        assert!(subject.clipboard_opt.is_some());
+        subject.clipboard_opt = Some(clipboard.clone());
+        subject.clipboard_timeout = Duration::from_millis(10);
+
+        let result = subject.execute(&mut context);
+
+        wait_for_clipboard_clears();
+        assert_eq!(result, Ok(()));
+        assert_eq!(
+            stdout_arc.lock().unwrap().get_string(),
+            "Booga:1234\nCopied to the clipboard; clearing it in 0 seconds\n"
+        );
+        assert_eq!(
+            *clipboard.contents.lock().unwrap(),
+            vec!["Booga:1234".to_string(), "".to_string()]
+        );
+    }
+
     #[test]
     fn descriptor_command_sad_path() {
         let transact_params_arc = Arc::new(Mutex::new(vec![]));
@@ -145,7 +231,7 @@ mod tests {
             .active_port_result(Some(port));
         let stdout_arc = context.stdout_arc();
         let stderr_arc = context.stderr_arc();
-        let subject = DescriptorCommand::new();
+        let subject = DescriptorCommand::new(&["descriptor".to_string()]).unwrap();
 
         let result = subject.execute(&mut context);
 
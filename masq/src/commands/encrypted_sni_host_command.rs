@@ -0,0 +1,283 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+
+use crate::command_context::CommandContext;
+use crate::commands::commands_common::CommandError::Payload;
+use crate::commands::commands_common::{
+    transaction, Command, CommandError, STANDARD_COMMAND_TIMEOUT_MILLIS,
+};
+use clap::{App, Arg, SubCommand};
+use masq_lib::messages::{
+    UiEncryptedSniHostRequest, UiEncryptedSniHostResponse, ENCRYPTED_SNI_ERROR,
+    NODE_NOT_RUNNING_ERROR,
+};
+use std::fmt::Debug;
+
+#[derive(Debug, PartialEq)]
+pub struct EncryptedSniHostCommand {
+    connection_id: u64,
+    host_name_opt: Option<String>,
+}
+
+pub fn encrypted_sni_host_subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("encrypted-sni-host")
+        .about("Tells the running MASQNode where to send a TLS connection that hides the name of its server, \
+        or refuses the connection if no host is given. Only useful when the Node was started with \
+        --encrypted-sni prompt, which announces each such connection as it arrives.")
+        .arg(Arg::with_name("connection")
+            .help("The number of the waiting connection, as the Node announced it")
+            .value_name("CONNECTION")
+            .required(true)
+            .index(1)
+            .validator(validate_connection_id))
+        .arg(Arg::with_name("host")
+            .help("The host the connection should go to")
+            .value_name("HOST")
+            .required(false)
+            .index(2))
+}
+
+fn validate_connection_id(connection_id: String) -> Result<(), String> {
+    match connection_id.parse::<u64>() {
+        Ok(_) => Ok(()),
+        Err(_) => Err(connection_id),
+    }
+}
+
+impl Command for EncryptedSniHostCommand {
+    fn execute(&self, context: &mut dyn CommandContext) -> Result<(), CommandError> {
+        let result: Result<UiEncryptedSniHostResponse, CommandError> = transaction(
+            UiEncryptedSniHostRequest {
+                connection_id: self.connection_id,
+                host_name_opt: self.host_name_opt.clone(),
+            },
+            context,
+            STANDARD_COMMAND_TIMEOUT_MILLIS,
+        );
+        match result {
+            Ok(_) => {
+                match &self.host_name_opt {
+                    Some(host_name) => writeln!(
+                        context.stdout(),
+                        "Connection {} is going to {}.",
+                        self.connection_id,
+                        host_name
+                    ),
+                    None => writeln!(
+                        context.stdout(),
+                        "Connection {} refused.",
+                        self.connection_id
+                    ),
+                }
+                .expect("write! failed");
+                Ok(())
+            }
+            Err(Payload(code, message)) if code == NODE_NOT_RUNNING_ERROR => {
+                writeln!(
+                    context.stderr(),
+                    "MASQNode is not running; therefore it has no connections waiting."
+                )
+                .expect("write! failed");
+                Err(Payload(code, message))
+            }
+            Err(Payload(code, message)) if code == ENCRYPTED_SNI_ERROR => {
+                writeln!(context.stderr(), "{}", message).expect("write! failed");
+                Err(Payload(code, message))
+            }
+            Err(e) => {
+                writeln!(
+                    context.stderr(),
+                    "Encrypted-SNI host request failed: {:?}",
+                    e
+                )
+                .expect("write! failed");
+                Err(e)
+            }
+        }
+    }
+}
+
+impl EncryptedSniHostCommand {
+    pub fn new(pieces: &[String]) -> Result<Self, String> {
+        let matches = match encrypted_sni_host_subcommand().get_matches_from_safe(pieces) {
+            Ok(matches) => matches,
+            Err(e) => return Err(format!("{}", e)),
+        };
+        Ok(Self {
+            connection_id: matches
+                .value_of("connection")
+                .expect("connection is not properly required")
+                .parse::<u64>()
+                .expect("connection is not properly validated"),
+            host_name_opt: matches.value_of("host").map(|host| host.to_string()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_context::ContextError;
+    use crate::command_context::ContextError::ConnectionDropped;
+    use crate::command_factory::{CommandFactory, CommandFactoryReal};
+    use crate::commands::commands_common::CommandError::ConnectionProblem;
+    use crate::test_utils::mocks::CommandContextMock;
+    use masq_lib::messages::ToMessageBody;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn testing_command_factory_here() {
+        let factory = CommandFactoryReal::new();
+        let mut context =
+            CommandContextMock::new().transact_result(Ok(UiEncryptedSniHostResponse {}.tmb(0)));
+        let subject = factory
+            .make(vec!["encrypted-sni-host".to_string(), "1".to_string()])
+            .unwrap();
+
+        let result = subject.execute(&mut context);
+
+        assert_eq!(result, Ok(()));
+    }
+
+    #[test]
+    fn command_line_is_interpreted_properly() {
+        let make = |args: &[&str]| {
+            EncryptedSniHostCommand::new(
+                &args
+                    .iter()
+                    .map(|arg| arg.to_string())
+                    .collect::<Vec<String>>(),
+            )
+        };
+
+        assert_eq!(
+            make(&["encrypted-sni-host", "12", "crypto.cloudflare.com"]),
+            Ok(EncryptedSniHostCommand {
+                connection_id: 12,
+                host_name_opt: Some("crypto.cloudflare.com".to_string())
+            })
+        );
+        assert_eq!(
+            make(&["encrypted-sni-host", "12"]),
+            Ok(EncryptedSniHostCommand {
+                connection_id: 12,
+                host_name_opt: None
+            })
+        );
+        assert_eq!(make(&["encrypted-sni-host"]).is_err(), true);
+        assert_eq!(make(&["encrypted-sni-host", "booga"]).is_err(), true);
+    }
+
+    #[test]
+    fn names_the_host() {
+        let transact_params_arc = Arc::new(Mutex::new(vec![]));
+        let mut context = CommandContextMock::new()
+            .transact_params(&transact_params_arc)
+            .transact_result(Ok(UiEncryptedSniHostResponse {}.tmb(0)));
+        let stdout_arc = context.stdout_arc();
+        let subject = EncryptedSniHostCommand {
+            connection_id: 12,
+            host_name_opt: Some("example.com".to_string()),
+        };
+
+        let result = subject.execute(&mut context);
+
+        assert_eq!(result, Ok(()));
+        let transact_params = transact_params_arc.lock().unwrap();
+        assert_eq!(
+            *transact_params,
+            vec![(
+                UiEncryptedSniHostRequest {
+                    connection_id: 12,
+                    host_name_opt: Some("example.com".to_string()),
+                }
+                .tmb(0),
+                STANDARD_COMMAND_TIMEOUT_MILLIS
+            )]
+        );
+        assert_eq!(
+            stdout_arc.lock().unwrap().get_string(),
+            "Connection 12 is going to example.com.\n"
+        );
+    }
+
+    #[test]
+    fn refuses_the_connection() {
+        let mut context =
+            CommandContextMock::new().transact_result(Ok(UiEncryptedSniHostResponse {}.tmb(0)));
+        let stdout_arc = context.stdout_arc();
+        let subject = EncryptedSniHostCommand {
+            connection_id: 12,
+            host_name_opt: None,
+        };
+
+        let result = subject.execute(&mut context);
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(
+            stdout_arc.lock().unwrap().get_string(),
+            "Connection 12 refused.\n"
+        );
+    }
+
+    #[test]
+    fn reports_connection_that_isnt_waiting() {
+        let mut context = CommandContextMock::new().transact_result(Err(
+            ContextError::PayloadError(ENCRYPTED_SNI_ERROR, "Not waiting".to_string()),
+        ));
+        let stderr_arc = context.stderr_arc();
+        let subject = EncryptedSniHostCommand {
+            connection_id: 12,
+            host_name_opt: None,
+        };
+
+        let result = subject.execute(&mut context);
+
+        assert_eq!(
+            result,
+            Err(Payload(ENCRYPTED_SNI_ERROR, "Not waiting".to_string()))
+        );
+        assert_eq!(stderr_arc.lock().unwrap().get_string(), "Not waiting\n");
+    }
+
+    #[test]
+    fn doesnt_work_if_node_is_not_running() {
+        let mut context = CommandContextMock::new().transact_result(Err(
+            ContextError::PayloadError(NODE_NOT_RUNNING_ERROR, "irrelevant".to_string()),
+        ));
+        let stderr_arc = context.stderr_arc();
+        let subject = EncryptedSniHostCommand {
+            connection_id: 12,
+            host_name_opt: None,
+        };
+
+        let result = subject.execute(&mut context);
+
+        assert_eq!(
+            result,
+            Err(Payload(NODE_NOT_RUNNING_ERROR, "irrelevant".to_string()))
+        );
+        assert_eq!(
+            stderr_arc.lock().unwrap().get_string(),
+            "MASQNode is not running; therefore it has no connections waiting.\n"
+        );
+    }
+
+    #[test]
+    fn encrypted_sni_host_command_sad_path() {
+        let mut context =
+            CommandContextMock::new().transact_result(Err(ConnectionDropped("Booga".to_string())));
+        let stderr_arc = context.stderr_arc();
+        let subject = EncryptedSniHostCommand {
+            connection_id: 12,
+            host_name_opt: None,
+        };
+
+        let result = subject.execute(&mut context);
+
+        assert_eq!(result, Err(ConnectionProblem("Booga".to_string())));
+        assert_eq!(
+            stderr_arc.lock().unwrap().get_string(),
+            "Encrypted-SNI host request failed: ConnectionProblem(\"Booga\")\n"
+        );
+    }
+}
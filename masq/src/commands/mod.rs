@@ -1,8 +1,15 @@
 // Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
 
+pub mod application_rules_command;
 pub mod commands_common;
 pub mod crash_command;
 pub mod descriptor_command;
+pub mod encrypted_sni_host_command;
+pub mod neighborhood_graph_command;
 pub mod setup_command;
 pub mod shutdown_command;
+pub mod speed_test_command;
+pub mod split_tunnel_rules_command;
 pub mod start_command;
+pub mod streams_command;
+pub mod tui_command;
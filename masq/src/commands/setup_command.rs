@@ -30,6 +30,7 @@ impl Command for SetupCommand {
     fn execute(&self, context: &mut dyn CommandContext) -> Result<(), CommandError> {
         let out_message = UiSetupRequest {
             values: self.values.clone(),
+            instance_opt: None,
         };
         let result: Result<UiSetupResponse, CommandError> =
             transaction(out_message, context, STANDARD_COMMAND_TIMEOUT_MILLIS);
@@ -113,8 +114,9 @@ impl SetupCommand {
         writeln!(stdout).expect("writeln! failed");
         if !inner.errors.is_empty() {
             writeln!(stdout, "ERRORS:").expect("writeln! failed");
-            inner.errors.into_iter().for_each(|(parameter, reason)| {
-                writeln!(stdout, "{:23}{}", parameter, reason).expect("writeln! failed")
+            inner.errors.into_iter().for_each(|error| {
+                writeln!(stdout, "{:23}{}", error.parameter, error.message)
+                    .expect("writeln! failed")
             });
             writeln!(stdout).expect("writeln! failed");
         }
@@ -136,7 +138,10 @@ mod tests {
     use crate::test_utils::mocks::{CommandContextMock, TestStreamFactory};
     use masq_lib::messages::ToMessageBody;
     use masq_lib::messages::UiSetupResponseValueStatus::{Configured, Default, Set};
-    use masq_lib::messages::{UiSetupRequest, UiSetupResponse, UiSetupResponseValue};
+    use masq_lib::messages::{
+        UiSetupRequest, UiSetupResponse, UiSetupResponseError, UiSetupResponseValue,
+    };
+    use masq_lib::shared_schema::ParamErrorCode::Invalid;
     use masq_lib::test_utils::utils::TEST_DEFAULT_CHAIN_NAME;
     use std::sync::{Arc, Mutex};
 
@@ -168,6 +173,7 @@ mod tests {
                     UiSetupResponseValue::new("neighborhood-mode", "zero-hop", Set),
                 ],
                 errors: vec![],
+                instance_opt: None,
             }
             .tmb(0)));
         let stdout_arc = context.stdout_arc();
@@ -196,7 +202,8 @@ mod tests {
                         UiSetupRequestValue::new("chain", TEST_DEFAULT_CHAIN_NAME),
                         UiSetupRequestValue::clear("log-level"),
                         UiSetupRequestValue::new("neighborhood-mode", "zero-hop"),
-                    ]
+                    ],
+                    instance_opt: None,
                 }
                 .tmb(0),
                 STANDARD_COMMAND_TIMEOUT_MILLIS
@@ -222,7 +229,12 @@ neighborhood-mode      zero-hop
                     UiSetupResponseValue::new("neighborhood-mode", "zero-hop", Configured),
                     UiSetupResponseValue::new("clandestine-port", "8534", Default),
                 ],
-                errors: vec![("ip".to_string(), "Nosir, I don't like it.".to_string())],
+                errors: vec![UiSetupResponseError::new(
+                    "ip",
+                    Invalid,
+                    "Nosir, I don't like it.",
+                )],
+                instance_opt: None,
             }
             .tmb(0)));
         let stdout_arc = context.stdout_arc();
@@ -254,7 +266,8 @@ neighborhood-mode      zero-hop
                         UiSetupRequestValue::new("clandestine-port", "8534"),
                         UiSetupRequestValue::clear("log-level"),
                         UiSetupRequestValue::new("neighborhood-mode", "zero-hop"),
-                    ]
+                    ],
+                    instance_opt: None,
                 }
                 .tmb(0),
                 STANDARD_COMMAND_TIMEOUT_MILLIS
@@ -283,7 +296,12 @@ NOTE: no changes were made to the setup because the Node is currently running.\n
                 UiSetupResponseValue::new("neighborhood-mode", "zero-hop", Configured),
                 UiSetupResponseValue::new("clandestine-port", "8534", Default),
             ],
-            errors: vec![("ip".to_string(), "Nosir, I don't like it.".to_string())],
+            errors: vec![UiSetupResponseError::new(
+                "ip",
+                Invalid,
+                "Nosir, I don't like it.",
+            )],
+            instance_opt: None,
         }
         .tmb(0);
         let (stream_factory, handle) = TestStreamFactory::new();
@@ -0,0 +1,201 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+
+use crate::command_context::CommandContext;
+use crate::commands::commands_common::CommandError::Payload;
+use crate::commands::commands_common::{transaction, Command, CommandError};
+use clap::{App, Arg, SubCommand};
+use masq_lib::messages::{UiSpeedTestRequest, UiSpeedTestResponse, NODE_NOT_RUNNING_ERROR};
+use std::fmt::Debug;
+
+// The Node answers within 30 seconds, whether or not all its probes have come back
+const SPEED_TEST_COMMAND_TIMEOUT_MILLIS: u64 = 35000;
+
+#[derive(Debug)]
+pub struct SpeedTestCommand {
+    hop_counts_opt: Option<Vec<usize>>,
+}
+
+pub fn speed_test_subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("speed-test")
+        .about("Measures how fast the running MASQNode's connection through the MASQ Network is, by timing probes \
+        over routes to exit Nodes and back: the round trip of an empty probe, and the throughput of a padded one. \
+        The relays on the routes are paid as for any other traffic. Only valid if Node is already running.")
+        .arg(Arg::with_name("hop-counts")
+            .help("Hop counts to test; without any, every hop count from 1 to the one the Node uses for its own traffic")
+            .index(1)
+            .multiple(true)
+            .validator(validate_hop_count))
+}
+
+fn validate_hop_count(hop_count: String) -> Result<(), String> {
+    match hop_count.parse::<usize>() {
+        Ok(_) => Ok(()),
+        Err(_) => Err(format!("'{}' is not a hop count", hop_count)),
+    }
+}
+
+impl Command for SpeedTestCommand {
+    fn execute(&self, context: &mut dyn CommandContext) -> Result<(), CommandError> {
+        let input = UiSpeedTestRequest {
+            hop_counts_opt: self.hop_counts_opt.clone(),
+        };
+        let output: Result<UiSpeedTestResponse, CommandError> =
+            transaction(input, context, SPEED_TEST_COMMAND_TIMEOUT_MILLIS);
+        match output {
+            Ok(response) => {
+                response.results.iter().for_each(|result| {
+                    writeln!(
+                        context.stdout(),
+                        "{} hop(s) to exit {}: {}ms round trip, {}kB/s",
+                        result.hop_count,
+                        result.exit_key,
+                        result.latency_ms,
+                        result.throughput_kbps
+                    )
+                    .expect("write! failed")
+                });
+                if !response.unavailable_hop_counts.is_empty() {
+                    writeln!(
+                        context.stdout(),
+                        "No route to an exit that answers probes, or no answer in time, for hop count(s): {}",
+                        response
+                            .unavailable_hop_counts
+                            .iter()
+                            .map(|hop_count| hop_count.to_string())
+                            .collect::<Vec<String>>()
+                            .join(", ")
+                    )
+                    .expect("write! failed");
+                }
+                Ok(())
+            }
+            Err(Payload(code, message)) if code == NODE_NOT_RUNNING_ERROR => {
+                writeln!(
+                    context.stderr(),
+                    "MASQNode is not running; therefore its speed cannot be tested."
+                )
+                .expect("write! failed");
+                Err(Payload(code, message))
+            }
+            Err(e) => {
+                writeln!(context.stderr(), "Speed test failed: {:?}", e).expect("write! failed");
+                Err(e)
+            }
+        }
+    }
+}
+
+impl SpeedTestCommand {
+    pub fn new(pieces: &[String]) -> Result<Self, String> {
+        let matches = match speed_test_subcommand().get_matches_from_safe(pieces) {
+            Ok(matches) => matches,
+            Err(e) => return Err(format!("{}", e)),
+        };
+        Ok(Self {
+            hop_counts_opt: matches.values_of("hop-counts").map(|values| {
+                values
+                    .map(|value| value.parse::<usize>().expect("Validator failed"))
+                    .collect()
+            }),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_context::ContextError;
+    use crate::command_factory::{CommandFactory, CommandFactoryReal};
+    use crate::test_utils::mocks::CommandContextMock;
+    use masq_lib::messages::{ToMessageBody, UiSpeedTestResult};
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn testing_command_factory_here() {
+        let factory = CommandFactoryReal::new();
+        let mut context = CommandContextMock::new().transact_result(Ok(UiSpeedTestResponse {
+            results: vec![],
+            unavailable_hop_counts: vec![],
+        }
+        .tmb(0)));
+        let subject = factory.make(vec!["speed-test".to_string()]).unwrap();
+
+        let result = subject.execute(&mut context);
+
+        assert_eq!(result, Ok(()));
+    }
+
+    #[test]
+    fn speed_test_command_happy_path() {
+        let transact_params_arc = Arc::new(Mutex::new(vec![]));
+        let mut context = CommandContextMock::new()
+            .transact_params(&transact_params_arc)
+            .transact_result(Ok(UiSpeedTestResponse {
+                results: vec![UiSpeedTestResult {
+                    hop_count: 1,
+                    exit_key: "AQIDBAUG".to_string(),
+                    latency_ms: 45,
+                    throughput_kbps: 1234,
+                }],
+                unavailable_hop_counts: vec![2, 3],
+            }
+            .tmb(0)));
+        let stdout_arc = context.stdout_arc();
+        let stderr_arc = context.stderr_arc();
+        let subject = SpeedTestCommand::new(&[
+            "speed-test".to_string(),
+            "1".to_string(),
+            "2".to_string(),
+            "3".to_string(),
+        ])
+        .unwrap();
+
+        let result = subject.execute(&mut context);
+
+        assert_eq!(result, Ok(()));
+        let transact_params = transact_params_arc.lock().unwrap();
+        assert_eq!(
+            *transact_params,
+            vec![(
+                UiSpeedTestRequest {
+                    hop_counts_opt: Some(vec![1, 2, 3])
+                }
+                .tmb(0),
+                SPEED_TEST_COMMAND_TIMEOUT_MILLIS
+            )]
+        );
+        assert_eq!(
+            stdout_arc.lock().unwrap().get_string(),
+            "1 hop(s) to exit AQIDBAUG: 45ms round trip, 1234kB/s\n\
+             No route to an exit that answers probes, or no answer in time, for hop count(s): 2, 3\n"
+        );
+        assert_eq!(stderr_arc.lock().unwrap().get_string(), String::new());
+    }
+
+    #[test]
+    fn speed_test_command_refuses_hop_counts_that_arent_numbers() {
+        let result = SpeedTestCommand::new(&["speed-test".to_string(), "booga".to_string()]);
+
+        assert!(result.unwrap_err().contains("'booga' is not a hop count"));
+    }
+
+    #[test]
+    fn doesnt_work_if_node_is_not_running() {
+        let mut context = CommandContextMock::new().transact_result(Err(
+            ContextError::PayloadError(NODE_NOT_RUNNING_ERROR, "irrelevant".to_string()),
+        ));
+        let stderr_arc = context.stderr_arc();
+        let subject = SpeedTestCommand::new(&["speed-test".to_string()]).unwrap();
+
+        let result = subject.execute(&mut context);
+
+        assert_eq!(
+            result,
+            Err(Payload(NODE_NOT_RUNNING_ERROR, "irrelevant".to_string()))
+        );
+        assert_eq!(
+            stderr_arc.lock().unwrap().get_string(),
+            "MASQNode is not running; therefore its speed cannot be tested.\n"
+        );
+    }
+}
@@ -0,0 +1,325 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+
+use crate::command_context::CommandContext;
+use crate::commands::commands_common::CommandError::Payload;
+use crate::commands::commands_common::{
+    transaction, Command, CommandError, STANDARD_COMMAND_TIMEOUT_MILLIS,
+};
+use clap::{App, Arg, ArgGroup, SubCommand};
+use masq_lib::messages::{
+    UiSetSplitTunnelRulesRequest, UiSetSplitTunnelRulesResponse, UiSplitTunnelRulesRequest,
+    UiSplitTunnelRulesResponse, NODE_NOT_RUNNING_ERROR, SPLIT_TUNNEL_RULES_ERROR,
+};
+use std::fmt::Debug;
+
+#[derive(Debug, PartialEq)]
+pub struct SplitTunnelRulesCommand {
+    rules_opt: Option<Vec<String>>,
+}
+
+pub fn split_tunnel_rules_subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("split-tunnel-rules")
+        .about("Displays or changes the split-tunnel rules of the running MASQNode. If there are any rules, only traffic \
+        to matching domains or networks goes through MASQ; everything else goes directly to its destination. Only valid \
+        if Node is already running.")
+        .arg(Arg::with_name("set")
+            .help("Replaces the current rules with these. Each rule is a domain name, which also matches all its \
+            subdomains, or a network in CIDR notation, such as 10.0.0.0/8, which matches destinations given as IP addresses")
+            .long("set")
+            .value_name("RULE")
+            .takes_value(true)
+            .multiple(true)
+            .use_delimiter(true))
+        .arg(Arg::with_name("clear")
+            .help("Removes all the rules, so that all traffic goes through MASQ")
+            .long("clear")
+            .takes_value(false))
+        .group(ArgGroup::with_name("change").args(&["set", "clear"]))
+}
+
+impl Command for SplitTunnelRulesCommand {
+    fn execute(&self, context: &mut dyn CommandContext) -> Result<(), CommandError> {
+        let result = match &self.rules_opt {
+            None => Self::display_rules(context),
+            Some(rules) => Self::change_rules(context, rules.clone()),
+        };
+        match result {
+            Ok(()) => Ok(()),
+            Err(Payload(code, message)) if code == NODE_NOT_RUNNING_ERROR => {
+                writeln!(
+                    context.stderr(),
+                    "MASQNode is not running; therefore its split-tunnel rules cannot be accessed."
+                )
+                .expect("write! failed");
+                Err(Payload(code, message))
+            }
+            Err(Payload(code, message)) if code == SPLIT_TUNNEL_RULES_ERROR => {
+                writeln!(context.stderr(), "{}", message).expect("write! failed");
+                Err(Payload(code, message))
+            }
+            Err(e) => {
+                writeln!(
+                    context.stderr(),
+                    "Split-tunnel rules request failed: {:?}",
+                    e
+                )
+                .expect("write! failed");
+                Err(e)
+            }
+        }
+    }
+}
+
+impl SplitTunnelRulesCommand {
+    pub fn new(pieces: &[String]) -> Result<Self, String> {
+        let matches = match split_tunnel_rules_subcommand().get_matches_from_safe(pieces) {
+            Ok(matches) => matches,
+            Err(e) => return Err(format!("{}", e)),
+        };
+        let rules_opt = if matches.is_present("clear") {
+            Some(vec![])
+        } else {
+            matches
+                .values_of("set")
+                .map(|values| values.map(|value| value.to_string()).collect())
+        };
+        Ok(Self { rules_opt })
+    }
+
+    fn display_rules(context: &mut dyn CommandContext) -> Result<(), CommandError> {
+        let response: UiSplitTunnelRulesResponse = transaction(
+            UiSplitTunnelRulesRequest {},
+            context,
+            STANDARD_COMMAND_TIMEOUT_MILLIS,
+        )?;
+        if response.rules.is_empty() {
+            writeln!(
+                context.stdout(),
+                "No split-tunnel rules: all traffic goes through MASQ."
+            )
+            .expect("write! failed");
+        } else {
+            writeln!(context.stdout(), "Only traffic to these goes through MASQ:")
+                .expect("write! failed");
+            response.rules.iter().for_each(|rule| {
+                writeln!(context.stdout(), "    {}", rule).expect("write! failed")
+            });
+        }
+        Ok(())
+    }
+
+    fn change_rules(
+        context: &mut dyn CommandContext,
+        rules: Vec<String>,
+    ) -> Result<(), CommandError> {
+        let _: UiSetSplitTunnelRulesResponse = transaction(
+            UiSetSplitTunnelRulesRequest { rules },
+            context,
+            STANDARD_COMMAND_TIMEOUT_MILLIS,
+        )?;
+        writeln!(context.stdout(), "Split-tunnel rules changed.").expect("write! failed");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_context::ContextError;
+    use crate::command_context::ContextError::ConnectionDropped;
+    use crate::command_factory::{CommandFactory, CommandFactoryReal};
+    use crate::commands::commands_common::CommandError::ConnectionProblem;
+    use crate::test_utils::mocks::CommandContextMock;
+    use masq_lib::messages::ToMessageBody;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn testing_command_factory_here() {
+        let factory = CommandFactoryReal::new();
+        let mut context = CommandContextMock::new()
+            .transact_result(Ok(UiSplitTunnelRulesResponse { rules: vec![] }.tmb(0)));
+        let subject = factory
+            .make(vec!["split-tunnel-rules".to_string()])
+            .unwrap();
+
+        let result = subject.execute(&mut context);
+
+        assert_eq!(result, Ok(()));
+    }
+
+    #[test]
+    fn command_line_is_interpreted_properly() {
+        let make = |args: &[&str]| {
+            SplitTunnelRulesCommand::new(
+                &args
+                    .iter()
+                    .map(|arg| arg.to_string())
+                    .collect::<Vec<String>>(),
+            )
+        };
+
+        assert_eq!(
+            make(&["split-tunnel-rules"]),
+            Ok(SplitTunnelRulesCommand { rules_opt: None })
+        );
+        assert_eq!(
+            make(&[
+                "split-tunnel-rules",
+                "--set",
+                "example.com,10.0.0.0/8,booga.org"
+            ]),
+            Ok(SplitTunnelRulesCommand {
+                rules_opt: Some(vec![
+                    "example.com".to_string(),
+                    "10.0.0.0/8".to_string(),
+                    "booga.org".to_string()
+                ])
+            })
+        );
+        assert_eq!(
+            make(&["split-tunnel-rules", "--clear"]),
+            Ok(SplitTunnelRulesCommand {
+                rules_opt: Some(vec![])
+            })
+        );
+        assert_eq!(
+            make(&["split-tunnel-rules", "--clear", "--set", "example.com"]).is_err(),
+            true
+        );
+    }
+
+    #[test]
+    fn displays_rules_when_there_are_some() {
+        let transact_params_arc = Arc::new(Mutex::new(vec![]));
+        let mut context = CommandContextMock::new()
+            .transact_params(&transact_params_arc)
+            .transact_result(Ok(UiSplitTunnelRulesResponse {
+                rules: vec!["example.com".to_string(), "10.0.0.0/8".to_string()],
+            }
+            .tmb(0)));
+        let stdout_arc = context.stdout_arc();
+        let stderr_arc = context.stderr_arc();
+        let subject = SplitTunnelRulesCommand { rules_opt: None };
+
+        let result = subject.execute(&mut context);
+
+        assert_eq!(result, Ok(()));
+        let transact_params = transact_params_arc.lock().unwrap();
+        assert_eq!(
+            *transact_params,
+            vec![(
+                UiSplitTunnelRulesRequest {}.tmb(0),
+                STANDARD_COMMAND_TIMEOUT_MILLIS
+            )]
+        );
+        assert_eq!(
+            stdout_arc.lock().unwrap().get_string(),
+            "Only traffic to these goes through MASQ:\n    example.com\n    10.0.0.0/8\n"
+        );
+        assert_eq!(stderr_arc.lock().unwrap().get_string(), String::new());
+    }
+
+    #[test]
+    fn displays_absence_of_rules() {
+        let mut context = CommandContextMock::new()
+            .transact_result(Ok(UiSplitTunnelRulesResponse { rules: vec![] }.tmb(0)));
+        let stdout_arc = context.stdout_arc();
+        let subject = SplitTunnelRulesCommand { rules_opt: None };
+
+        let result = subject.execute(&mut context);
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(
+            stdout_arc.lock().unwrap().get_string(),
+            "No split-tunnel rules: all traffic goes through MASQ.\n"
+        );
+    }
+
+    #[test]
+    fn changes_rules() {
+        let transact_params_arc = Arc::new(Mutex::new(vec![]));
+        let mut context = CommandContextMock::new()
+            .transact_params(&transact_params_arc)
+            .transact_result(Ok(UiSetSplitTunnelRulesResponse {}.tmb(0)));
+        let stdout_arc = context.stdout_arc();
+        let subject = SplitTunnelRulesCommand {
+            rules_opt: Some(vec!["example.com".to_string()]),
+        };
+
+        let result = subject.execute(&mut context);
+
+        assert_eq!(result, Ok(()));
+        let transact_params = transact_params_arc.lock().unwrap();
+        assert_eq!(
+            *transact_params,
+            vec![(
+                UiSetSplitTunnelRulesRequest {
+                    rules: vec!["example.com".to_string()]
+                }
+                .tmb(0),
+                STANDARD_COMMAND_TIMEOUT_MILLIS
+            )]
+        );
+        assert_eq!(
+            stdout_arc.lock().unwrap().get_string(),
+            "Split-tunnel rules changed.\n"
+        );
+    }
+
+    #[test]
+    fn reports_rejected_rules() {
+        let mut context = CommandContextMock::new().transact_result(Err(
+            ContextError::PayloadError(SPLIT_TUNNEL_RULES_ERROR, "Bad rule".to_string()),
+        ));
+        let stdout_arc = context.stdout_arc();
+        let stderr_arc = context.stderr_arc();
+        let subject = SplitTunnelRulesCommand {
+            rules_opt: Some(vec!["booga/8".to_string()]),
+        };
+
+        let result = subject.execute(&mut context);
+
+        assert_eq!(
+            result,
+            Err(Payload(SPLIT_TUNNEL_RULES_ERROR, "Bad rule".to_string()))
+        );
+        assert_eq!(stdout_arc.lock().unwrap().get_string(), String::new());
+        assert_eq!(stderr_arc.lock().unwrap().get_string(), "Bad rule\n");
+    }
+
+    #[test]
+    fn doesnt_work_if_node_is_not_running() {
+        let mut context = CommandContextMock::new().transact_result(Err(
+            ContextError::PayloadError(NODE_NOT_RUNNING_ERROR, "irrelevant".to_string()),
+        ));
+        let stderr_arc = context.stderr_arc();
+        let subject = SplitTunnelRulesCommand { rules_opt: None };
+
+        let result = subject.execute(&mut context);
+
+        assert_eq!(
+            result,
+            Err(Payload(NODE_NOT_RUNNING_ERROR, "irrelevant".to_string()))
+        );
+        assert_eq!(
+            stderr_arc.lock().unwrap().get_string(),
+            "MASQNode is not running; therefore its split-tunnel rules cannot be accessed.\n"
+        );
+    }
+
+    #[test]
+    fn split_tunnel_rules_command_sad_path() {
+        let mut context =
+            CommandContextMock::new().transact_result(Err(ConnectionDropped("Booga".to_string())));
+        let stderr_arc = context.stderr_arc();
+        let subject = SplitTunnelRulesCommand { rules_opt: None };
+
+        let result = subject.execute(&mut context);
+
+        assert_eq!(result, Err(ConnectionProblem("Booga".to_string())));
+        assert_eq!(
+            stderr_arc.lock().unwrap().get_string(),
+            "Split-tunnel rules request failed: ConnectionProblem(\"Booga\")\n"
+        );
+    }
+}
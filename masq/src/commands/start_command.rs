@@ -19,7 +19,7 @@ pub struct StartCommand {}
 
 impl Command for StartCommand {
     fn execute(&self, context: &mut dyn CommandContext) -> Result<(), CommandError> {
-        let out_message = UiStartOrder {};
+        let out_message = UiStartOrder { instance_opt: None };
         let result: Result<UiStartResponse, CommandError> =
             transaction(out_message, context, STANDARD_COMMAND_TIMEOUT_MILLIS);
         match result {
@@ -76,7 +76,10 @@ mod tests {
         let transact_params = transact_params_arc.lock().unwrap();
         assert_eq!(
             *transact_params,
-            vec![(UiStartOrder {}.tmb(0), STANDARD_COMMAND_TIMEOUT_MILLIS)]
+            vec![(
+                UiStartOrder { instance_opt: None }.tmb(0),
+                STANDARD_COMMAND_TIMEOUT_MILLIS
+            )]
         );
         assert_eq!(
             stdout_arc.lock().unwrap().get_string(),
@@ -0,0 +1,222 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+
+use crate::command_context::CommandContext;
+use crate::commands::commands_common::CommandError::Payload;
+use crate::commands::commands_common::{
+    transaction, Command, CommandError, STANDARD_COMMAND_TIMEOUT_MILLIS,
+};
+use clap::{App, SubCommand};
+use masq_lib::messages::{UiStream, UiStreamsRequest, UiStreamsResponse, NODE_NOT_RUNNING_ERROR};
+use std::fmt::Debug;
+
+#[derive(Debug)]
+pub struct StreamsCommand {}
+
+pub fn streams_subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("streams")
+        .about("Displays the streams the running MASQNode is consuming for its own clients and serving as an exit \
+        Node for others: their protocols, target hosts, hop counts, bytes out and in, and ages. Only valid if Node \
+        is already running.")
+}
+
+impl Command for StreamsCommand {
+    fn execute(&self, context: &mut dyn CommandContext) -> Result<(), CommandError> {
+        let output: Result<UiStreamsResponse, CommandError> = transaction(
+            UiStreamsRequest {},
+            context,
+            STANDARD_COMMAND_TIMEOUT_MILLIS,
+        );
+        match output {
+            Ok(response) => {
+                Self::write_streams(context, "Consuming", &response.consuming);
+                Self::write_streams(context, "Serving", &response.serving);
+                Ok(())
+            }
+            Err(Payload(code, message)) if code == NODE_NOT_RUNNING_ERROR => {
+                writeln!(
+                    context.stderr(),
+                    "MASQNode is not running; therefore its streams cannot be displayed."
+                )
+                .expect("write! failed");
+                Err(Payload(code, message))
+            }
+            Err(e) => {
+                writeln!(context.stderr(), "Streams retrieval failed: {:?}", e)
+                    .expect("write! failed");
+                Err(e)
+            }
+        }
+    }
+}
+
+impl Default for StreamsCommand {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StreamsCommand {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    fn write_streams(context: &mut dyn CommandContext, heading: &str, streams: &[UiStream]) {
+        if streams.is_empty() {
+            writeln!(context.stdout(), "{} no streams.", heading).expect("write! failed");
+            return;
+        }
+        writeln!(context.stdout(), "{} {} stream(s):", heading, streams.len())
+            .expect("write! failed");
+        streams.iter().for_each(|stream| {
+            let hops = match stream.hop_count_opt {
+                Some(hop_count) => format!("{} hops, ", hop_count),
+                None => String::new(),
+            };
+            writeln!(
+                context.stdout(),
+                "    {} {} {}: {}{} bytes out, {} bytes in, open {}s",
+                stream.stream_key,
+                stream.protocol,
+                stream
+                    .target_host_opt
+                    .as_deref()
+                    .unwrap_or("<unknown host>"),
+                hops,
+                stream.bytes_out,
+                stream.bytes_in,
+                stream.age_seconds
+            )
+            .expect("write! failed")
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_context::ContextError;
+    use crate::command_context::ContextError::ConnectionDropped;
+    use crate::command_factory::{CommandFactory, CommandFactoryReal};
+    use crate::commands::commands_common::CommandError::ConnectionProblem;
+    use crate::test_utils::mocks::CommandContextMock;
+    use masq_lib::messages::ToMessageBody;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn testing_command_factory_here() {
+        let factory = CommandFactoryReal::new();
+        let mut context = CommandContextMock::new().transact_result(Ok(UiStreamsResponse {
+            consuming: vec![],
+            serving: vec![],
+        }
+        .tmb(0)));
+        let subject = factory.make(vec!["streams".to_string()]).unwrap();
+
+        let result = subject.execute(&mut context);
+
+        assert_eq!(result, Ok(()));
+    }
+
+    #[test]
+    fn streams_command_happy_path() {
+        let transact_params_arc = Arc::new(Mutex::new(vec![]));
+        let mut context = CommandContextMock::new()
+            .transact_params(&transact_params_arc)
+            .transact_result(Ok(UiStreamsResponse {
+                consuming: vec![UiStream {
+                    stream_key: "Booga".to_string(),
+                    protocol: "TLS".to_string(),
+                    target_host_opt: Some("example.com".to_string()),
+                    bytes_out: 1234,
+                    bytes_in: 56789,
+                    hop_count_opt: Some(3),
+                    age_seconds: 65,
+                }],
+                serving: vec![UiStream {
+                    stream_key: "Agoob".to_string(),
+                    protocol: "HTTP".to_string(),
+                    target_host_opt: None,
+                    bytes_out: 12,
+                    bytes_in: 0,
+                    hop_count_opt: None,
+                    age_seconds: 2,
+                }],
+            }
+            .tmb(0)));
+        let stdout_arc = context.stdout_arc();
+        let stderr_arc = context.stderr_arc();
+        let subject = StreamsCommand::new();
+
+        let result = subject.execute(&mut context);
+
+        assert_eq!(result, Ok(()));
+        let transact_params = transact_params_arc.lock().unwrap();
+        assert_eq!(
+            *transact_params,
+            vec![(UiStreamsRequest {}.tmb(0), STANDARD_COMMAND_TIMEOUT_MILLIS)]
+        );
+        assert_eq!(
+            stdout_arc.lock().unwrap().get_string(),
+            "Consuming 1 stream(s):\n    \
+             Booga TLS example.com: 3 hops, 1234 bytes out, 56789 bytes in, open 65s\n\
+             Serving 1 stream(s):\n    \
+             Agoob HTTP <unknown host>: 12 bytes out, 0 bytes in, open 2s\n"
+        );
+        assert_eq!(stderr_arc.lock().unwrap().get_string(), String::new());
+    }
+
+    #[test]
+    fn streams_command_reports_absence_of_streams() {
+        let mut context = CommandContextMock::new().transact_result(Ok(UiStreamsResponse {
+            consuming: vec![],
+            serving: vec![],
+        }
+        .tmb(0)));
+        let stdout_arc = context.stdout_arc();
+        let subject = StreamsCommand::new();
+
+        let result = subject.execute(&mut context);
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(
+            stdout_arc.lock().unwrap().get_string(),
+            "Consuming no streams.\nServing no streams.\n"
+        );
+    }
+
+    #[test]
+    fn doesnt_work_if_node_is_not_running() {
+        let mut context = CommandContextMock::new().transact_result(Err(
+            ContextError::PayloadError(NODE_NOT_RUNNING_ERROR, "irrelevant".to_string()),
+        ));
+        let stderr_arc = context.stderr_arc();
+        let subject = StreamsCommand::new();
+
+        let result = subject.execute(&mut context);
+
+        assert_eq!(
+            result,
+            Err(Payload(NODE_NOT_RUNNING_ERROR, "irrelevant".to_string()))
+        );
+        assert_eq!(
+            stderr_arc.lock().unwrap().get_string(),
+            "MASQNode is not running; therefore its streams cannot be displayed.\n"
+        );
+    }
+
+    #[test]
+    fn streams_command_sad_path() {
+        let mut context =
+            CommandContextMock::new().transact_result(Err(ConnectionDropped("Booga".to_string())));
+        let stderr_arc = context.stderr_arc();
+        let subject = StreamsCommand::new();
+
+        let result = subject.execute(&mut context);
+
+        assert_eq!(result, Err(ConnectionProblem("Booga".to_string())));
+        assert_eq!(
+            stderr_arc.lock().unwrap().get_string(),
+            "Streams retrieval failed: ConnectionProblem(\"Booga\")\n"
+        );
+    }
+}
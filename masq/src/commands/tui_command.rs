@@ -0,0 +1,439 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+
+use crate::command_context::CommandContext;
+use crate::commands::commands_common::CommandError::{ConnectionProblem, Payload};
+use crate::commands::commands_common::{
+    transaction, Command, CommandError, STANDARD_COMMAND_TIMEOUT_MILLIS,
+};
+use clap::{App, Arg, SubCommand};
+use masq_lib::messages::{
+    FromMessageBody, ToMessageBody, UiDescriptorRequest, UiDescriptorResponse, UiFinancialsRequest,
+    UiFinancialsResponse, UiHealthRequest, UiHealthResponse, UiNeighborhoodGraphRequest,
+    UiNeighborhoodGraphResponse, UiStream, UiStreamsRequest, UiStreamsResponse,
+    NODE_NOT_RUNNING_ERROR,
+};
+use std::fmt::Debug;
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_TUI_INTERVAL_SECS: u64 = 2;
+// Every refresh asks the Node five questions; more often than this would only keep it busy
+const MINIMUM_TUI_INTERVAL_SECS: u64 = 1;
+// Rows shown in a list before the rest are summarized, so that a screen fits in a terminal
+const MAX_TUI_ROWS: usize = 10;
+const CLEAR_SCREEN: &str = "\x1B[2J\x1B[H";
+
+#[derive(Debug)]
+pub struct TuiCommand {
+    interval: Duration,
+    frames_opt: Option<usize>,
+}
+
+pub fn tui_subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("tui")
+        .about("Shows the running MASQNode's health, neighbors, streams, and financials on a screen that \
+        refreshes itself, for operators who reach their Nodes over SSH. Press Ctrl-C to leave it. 'masq --tui' \
+        does the same thing.")
+        .arg(Arg::with_name("interval")
+            .help("Seconds between refreshes")
+            .long("interval")
+            .value_name("SECONDS")
+            .default_value("2")
+            .validator(validate_interval))
+}
+
+fn validate_interval(seconds: String) -> Result<(), String> {
+    match seconds.parse::<u64>() {
+        Ok(seconds) if seconds >= MINIMUM_TUI_INTERVAL_SECS => Ok(()),
+        _ => Err(format!(
+            "Interval must be a whole number of seconds, at least {}, not '{}'",
+            MINIMUM_TUI_INTERVAL_SECS, seconds
+        )),
+    }
+}
+
+impl Command for TuiCommand {
+    fn execute(&self, context: &mut dyn CommandContext) -> Result<(), CommandError> {
+        let mut frames = 0;
+        loop {
+            let started = Instant::now();
+            let screen = Self::render(context)?;
+            write!(context.stdout(), "{}{}", CLEAR_SCREEN, screen).expect("write! failed");
+            context.stdout().flush().expect("flush failed");
+            frames += 1;
+            if self.frames_opt == Some(frames) {
+                return Ok(());
+            }
+            if let Some(remaining) = self.interval.checked_sub(started.elapsed()) {
+                thread::sleep(remaining)
+            }
+        }
+    }
+}
+
+impl TuiCommand {
+    pub fn new(pieces: &[String]) -> Result<Self, String> {
+        let matches = match tui_subcommand().get_matches_from_safe(pieces) {
+            Ok(matches) => matches,
+            Err(e) => return Err(format!("{}", e)),
+        };
+        let seconds = matches
+            .value_of("interval")
+            .expect("interval parameter is not properly defaulted")
+            .parse::<u64>()
+            .expect("interval parameter is not properly validated");
+        Ok(Self {
+            interval: Duration::from_secs(seconds),
+            frames_opt: None,
+        })
+    }
+
+    // One screenful. Only losing the connection ends the display: a Node that isn't running yet
+    // or doesn't answer one of the questions just leaves a gap until the next refresh.
+    fn render(context: &mut dyn CommandContext) -> Result<String, CommandError> {
+        let mut screen = String::new();
+        let health = match Self::ask::<_, UiHealthResponse>(UiHealthRequest {}, context)? {
+            Ok(health) => health,
+            Err(Payload(code, _)) if code == NODE_NOT_RUNNING_ERROR => {
+                return Ok(
+                    "MASQNode is not running; waiting for it to start. Press Ctrl-C to quit.\n"
+                        .to_string(),
+                )
+            }
+            Err(e) => return Ok(format!("Node isn't answering: {}\n", e)),
+        };
+        screen.push_str(&Self::render_health(&health));
+        screen.push_str(&match Self::ask::<_, UiDescriptorResponse>(
+            UiDescriptorRequest {},
+            context,
+        )? {
+            Ok(response) => format!("Descriptor: {}\n", response.node_descriptor),
+            Err(e) => format!("Descriptor: unavailable ({})\n", e),
+        });
+        screen.push_str(&match Self::ask::<_, UiNeighborhoodGraphResponse>(
+            UiNeighborhoodGraphRequest {},
+            context,
+        )? {
+            Ok(response) => Self::render_neighbors(&response),
+            Err(e) => format!("\nNeighbors: unavailable ({})\n", e),
+        });
+        screen.push_str(
+            &match Self::ask::<_, UiStreamsResponse>(UiStreamsRequest {}, context)? {
+                Ok(response) => format!(
+                    "{}{}",
+                    Self::render_streams("Consuming", &response.consuming),
+                    Self::render_streams("Serving", &response.serving)
+                ),
+                Err(e) => format!("\nStreams: unavailable ({})\n", e),
+            },
+        );
+        screen.push_str(&match Self::ask::<_, UiFinancialsResponse>(
+            UiFinancialsRequest {
+                payable_minimum_amount: 0,
+                payable_maximum_age: u64::MAX,
+                receivable_minimum_amount: 0,
+                receivable_maximum_age: u64::MAX,
+            },
+            context,
+        )? {
+            Ok(response) => format!(
+                "\nFinancials: {} owed by this Node in {} account(s), {} owed to it in {} account(s)\n",
+                response.total_payable,
+                response.payables.len(),
+                response.total_receivable,
+                response.receivables.len()
+            ),
+            Err(e) => format!("\nFinancials: unavailable ({})\n", e),
+        });
+        screen.push_str("\nPress Ctrl-C to quit.\n");
+        Ok(screen)
+    }
+
+    // The outer error ends the display; the inner one is just shown
+    #[allow(clippy::type_complexity)]
+    fn ask<I, O>(
+        request: I,
+        context: &mut dyn CommandContext,
+    ) -> Result<Result<O, CommandError>, CommandError>
+    where
+        I: ToMessageBody,
+        O: FromMessageBody,
+    {
+        match transaction(request, context, STANDARD_COMMAND_TIMEOUT_MILLIS) {
+            Err(ConnectionProblem(msg)) => Err(ConnectionProblem(msg)),
+            result => Ok(result),
+        }
+    }
+
+    fn render_health(health: &UiHealthResponse) -> String {
+        let status = match (health.healthy, health.ready) {
+            (true, true) => "healthy and ready".to_string(),
+            _ => format!(
+                "{}; failing {}",
+                if health.healthy {
+                    "healthy but not ready"
+                } else {
+                    "unhealthy"
+                },
+                health
+                    .checks
+                    .iter()
+                    .filter(|check| !check.passed)
+                    .map(|check| format!("{} ({})", check.name, check.detail))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+        };
+        format!("MASQNode: {}\n", status)
+    }
+
+    fn render_neighbors(graph: &UiNeighborhoodGraphResponse) -> String {
+        let local_key = match graph.nodes.iter().find(|node| node.is_local) {
+            Some(node) => &node.public_key,
+            None => return "\nNo neighbors.\n".to_string(),
+        };
+        let neighbors = graph
+            .edges
+            .iter()
+            .filter(|edge| &edge.from == local_key)
+            .filter_map(|edge| graph.nodes.iter().find(|node| node.public_key == edge.to))
+            .collect::<Vec<_>>();
+        if neighbors.is_empty() {
+            return "\nNo neighbors.\n".to_string();
+        }
+        let mut result = format!(
+            "\n{} neighbor(s) of {} Node(s) known:\n",
+            neighbors.len(),
+            graph.nodes.len()
+        );
+        let rows = neighbors.iter().map(|node| {
+            format!(
+                "    {}{}{}\n",
+                node.public_key,
+                if node.accepts_connections {
+                    ", accepts connections"
+                } else {
+                    ""
+                },
+                if node.routes_data {
+                    ", routes data"
+                } else {
+                    ""
+                }
+            )
+        });
+        result.push_str(&Self::limit_rows(rows.collect()));
+        result
+    }
+
+    fn render_streams(heading: &str, streams: &[UiStream]) -> String {
+        if streams.is_empty() {
+            return format!("\n{} no streams.\n", heading);
+        }
+        let mut result = format!("\n{} {} stream(s):\n", heading, streams.len());
+        let rows = streams.iter().map(|stream| {
+            format!(
+                "    {} {}: {} bytes out, {} bytes in, open {}s\n",
+                stream.protocol,
+                stream
+                    .target_host_opt
+                    .as_deref()
+                    .unwrap_or("<unknown host>"),
+                stream.bytes_out,
+                stream.bytes_in,
+                stream.age_seconds
+            )
+        });
+        result.push_str(&Self::limit_rows(rows.collect()));
+        result
+    }
+
+    fn limit_rows(rows: Vec<String>) -> String {
+        let mut result = rows.iter().take(MAX_TUI_ROWS).cloned().collect::<String>();
+        if rows.len() > MAX_TUI_ROWS {
+            result.push_str(&format!("    ...and {} more\n", rows.len() - MAX_TUI_ROWS));
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_context::ContextError::{ConnectionDropped, PayloadError};
+    use crate::command_factory::{CommandFactory, CommandFactoryReal};
+    use crate::test_utils::mocks::CommandContextMock;
+    use masq_lib::messages::{
+        UiHealthCheck, UiNeighborhoodGraphEdge, UiNeighborhoodGraphNode, UiPayableAccount,
+        UiRatePack,
+    };
+    use std::sync::{Arc, Mutex};
+
+    fn graph_node(public_key: &str, is_local: bool) -> UiNeighborhoodGraphNode {
+        UiNeighborhoodGraphNode {
+            public_key: public_key.to_string(),
+            is_local,
+            version: 0,
+            accepts_connections: true,
+            routes_data: !is_local,
+            rate_pack: UiRatePack {
+                routing_byte_rate: 1,
+                routing_service_rate: 2,
+                exit_byte_rate: 3,
+                exit_service_rate: 4,
+            },
+            country_code_opt: None,
+        }
+    }
+
+    #[test]
+    fn the_factory_makes_a_tui_with_the_requested_interval() {
+        let factory = CommandFactoryReal::new();
+
+        let result = factory.make(vec![
+            "tui".to_string(),
+            "--interval".to_string(),
+            "5".to_string(),
+        ]);
+
+        assert_eq!(
+            format!("{:?}", result.unwrap()),
+            "TuiCommand { interval: 5s, frames_opt: None }"
+        );
+        assert_eq!(
+            TuiCommand::new(&["tui".to_string()]).unwrap().interval,
+            Duration::from_secs(DEFAULT_TUI_INTERVAL_SECS)
+        );
+        assert!(
+            TuiCommand::new(&["tui".to_string(), "--interval".to_string(), "0".to_string()])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn a_screen_shows_health_neighbors_streams_and_financials() {
+        let transact_params_arc = Arc::new(Mutex::new(vec![]));
+        let mut context = CommandContextMock::new()
+            .transact_params(&transact_params_arc)
+            .transact_result(Ok(UiHealthResponse {
+                healthy: true,
+                ready: false,
+                checks: vec![UiHealthCheck {
+                    name: "neighbors".to_string(),
+                    passed: false,
+                    detail: "no neighbors yet".to_string(),
+                }],
+            }
+            .tmb(0)))
+            .transact_result(Ok(UiDescriptorResponse {
+                node_descriptor: "AQIDBA@1.2.3.4:1234".to_string(),
+            }
+            .tmb(0)))
+            .transact_result(Ok(UiNeighborhoodGraphResponse {
+                nodes: vec![
+                    graph_node("AQIDBA", true),
+                    graph_node("BAUGBw", false),
+                    graph_node("CAkKCw", false),
+                ],
+                edges: vec![
+                    UiNeighborhoodGraphEdge {
+                        from: "AQIDBA".to_string(),
+                        to: "BAUGBw".to_string(),
+                    },
+                    UiNeighborhoodGraphEdge {
+                        from: "BAUGBw".to_string(),
+                        to: "CAkKCw".to_string(),
+                    },
+                ],
+                dot_graph: String::new(),
+            }
+            .tmb(0)))
+            .transact_result(Ok(UiStreamsResponse {
+                consuming: vec![UiStream {
+                    stream_key: "Booga".to_string(),
+                    protocol: "TLS".to_string(),
+                    target_host_opt: Some("example.com".to_string()),
+                    bytes_out: 1234,
+                    bytes_in: 56789,
+                    hop_count_opt: Some(3),
+                    age_seconds: 65,
+                }],
+                serving: vec![],
+            }
+            .tmb(0)))
+            .transact_result(Ok(UiFinancialsResponse {
+                payables: vec![UiPayableAccount {
+                    wallet: "0x01".to_string(),
+                    age: 10,
+                    amount: 4321,
+                    pending_transaction: None,
+                }],
+                total_payable: 4321,
+                receivables: vec![],
+                total_receivable: 0,
+            }
+            .tmb(0)));
+        let stdout_arc = context.stdout_arc();
+        let subject = TuiCommand {
+            interval: Duration::from_secs(1),
+            frames_opt: Some(1),
+        };
+
+        let result = subject.execute(&mut context);
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(
+            stdout_arc.lock().unwrap().get_string(),
+            format!(
+                "{}MASQNode: healthy but not ready; failing neighbors (no neighbors yet)\n\
+                 Descriptor: AQIDBA@1.2.3.4:1234\n\
+                 \n\
+                 1 neighbor(s) of 3 Node(s) known:\n    BAUGBw, accepts connections, routes data\n\
+                 \n\
+                 Consuming 1 stream(s):\n    TLS example.com: 1234 bytes out, 56789 bytes in, open 65s\n\
+                 \n\
+                 Serving no streams.\n\
+                 \n\
+                 Financials: 4321 owed by this Node in 1 account(s), 0 owed to it in 0 account(s)\n\
+                 \n\
+                 Press Ctrl-C to quit.\n",
+                CLEAR_SCREEN
+            )
+        );
+        assert_eq!(transact_params_arc.lock().unwrap().len(), 5);
+    }
+
+    #[test]
+    fn a_node_that_isnt_running_is_waited_for_and_a_lost_connection_ends_the_display() {
+        let mut context = CommandContextMock::new()
+            .transact_result(Ok(UiHealthResponse {
+                healthy: true,
+                ready: true,
+                checks: vec![],
+            }
+            .tmb(0)))
+            .transact_result(Err(ConnectionDropped("Booga".to_string())));
+        let stdout_arc = context.stdout_arc();
+        let subject = TuiCommand {
+            interval: Duration::from_secs(1),
+            frames_opt: None,
+        };
+
+        let result = subject.execute(&mut context);
+
+        assert_eq!(result, Err(ConnectionProblem("Booga".to_string())));
+        assert_eq!(stdout_arc.lock().unwrap().get_string(), "");
+        assert_eq!(
+            TuiCommand::render(
+                &mut CommandContextMock::new().transact_result(Err(PayloadError(
+                    NODE_NOT_RUNNING_ERROR,
+                    "not running".to_string()
+                )))
+            ),
+            Ok(
+                "MASQNode is not running; waiting for it to start. Press Ctrl-C to quit.\n"
+                    .to_string()
+            )
+        );
+    }
+}
@@ -2,8 +2,14 @@
 
 use crate::commands::setup_command::SetupCommand;
 use crate::notifications::crashed_notification::CrashNotifier;
+use crate::notifications::encrypted_sni_notification::EncryptedSniNotifier;
+use crate::notifications::restarted_notification::RestartNotifier;
+use crate::notifications::update_notification::UpdateNotifier;
 use crossbeam_channel::{unbounded, Receiver, RecvError, Sender};
-use masq_lib::messages::{UiNodeCrashedBroadcast, UiSetupBroadcast};
+use masq_lib::messages::{
+    UiEncryptedSniBroadcast, UiNodeCrashedBroadcast, UiNodeRestartedBroadcast, UiSetupBroadcast,
+    UiUpdateAvailableBroadcast,
+};
 use masq_lib::ui_gateway::MessageBody;
 use std::fmt::Debug;
 use std::io::Write;
@@ -71,6 +77,15 @@ impl BroadcastHandlerReal {
             o if o == UiNodeCrashedBroadcast::type_opcode() => {
                 CrashNotifier::handle_broadcast(message_body, stdout, stderr)
             }
+            o if o == UiNodeRestartedBroadcast::type_opcode() => {
+                RestartNotifier::handle_broadcast(message_body, stdout, stderr)
+            }
+            o if o == UiEncryptedSniBroadcast::type_opcode() => {
+                EncryptedSniNotifier::handle_broadcast(message_body, stdout, stderr)
+            }
+            o if o == UiUpdateAvailableBroadcast::type_opcode() => {
+                UpdateNotifier::handle_broadcast(message_body, stdout, stderr)
+            }
             opcode => {
                 write!(
                     stderr,
@@ -135,6 +150,7 @@ mod tests {
             running: true,
             values: vec![],
             errors: vec![],
+            instance_opt: None,
         }
         .tmb(0);
 
@@ -169,6 +185,10 @@ mod tests {
         let message = UiNodeCrashedBroadcast {
             process_id: 1234,
             crash_reason: CrashReason::Unrecognized("Unknown crash reason".to_string()),
+            exit_code_opt: None,
+            last_log_lines: vec![],
+            restart_delay_secs_opt: None,
+            instance_opt: None,
         }
         .tmb(0);
 
@@ -187,6 +207,94 @@ mod tests {
         );
     }
 
+    #[test]
+    fn broadcast_of_restarted_triggers_correct_handler() {
+        let (factory, handle) = TestStreamFactory::new();
+        // This thread will leak, and will only stop when the tests stop running.
+        let subject = BroadcastHandlerReal::new().start(Box::new(factory));
+        let message = UiNodeRestartedBroadcast {
+            new_process_id: 2345,
+            redirect_ui_port: 5334,
+            attempt: 1,
+            instance_opt: None,
+        }
+        .tmb(0);
+
+        subject.send(message);
+
+        let stdout = handle.stdout_so_far();
+        assert_eq!(
+            stdout.contains("running as process 2345"),
+            true,
+            "stdout: '{}' doesn't contain 'running as process 2345'",
+            stdout
+        );
+        assert_eq!(
+            handle.stderr_so_far(),
+            "".to_string(),
+            "stderr: '{}'",
+            stdout
+        );
+    }
+
+    #[test]
+    fn broadcast_of_encrypted_sni_triggers_correct_handler() {
+        let (factory, handle) = TestStreamFactory::new();
+        // This thread will leak, and will only stop when the tests stop running.
+        let subject = BroadcastHandlerReal::new().start(Box::new(factory));
+        let message = UiEncryptedSniBroadcast {
+            connection_id: 12,
+            client_address: "127.0.0.1:5678".to_string(),
+        }
+        .tmb(0);
+
+        subject.send(message);
+
+        let stdout = handle.stdout_so_far();
+        assert_eq!(
+            stdout.contains("encrypted-sni-host 12 <HOST>"),
+            true,
+            "stdout: '{}' doesn't contain 'encrypted-sni-host 12 <HOST>'",
+            stdout
+        );
+        assert_eq!(
+            handle.stderr_so_far(),
+            "".to_string(),
+            "stderr: '{}'",
+            stdout
+        );
+    }
+
+    #[test]
+    fn broadcast_of_update_available_triggers_correct_handler() {
+        let (factory, handle) = TestStreamFactory::new();
+        // This thread will leak, and will only stop when the tests stop running.
+        let subject = BroadcastHandlerReal::new().start(Box::new(factory));
+        let message = UiUpdateAvailableBroadcast {
+            current_version: "1.0.0".to_string(),
+            latest_version: "1.1.0".to_string(),
+            release_notes_url: "https://releases.example.com/1.1.0".to_string(),
+            security: false,
+        }
+        .tmb(0);
+
+        subject.send(message);
+
+        let stdout = handle.stdout_so_far();
+        assert_eq!(
+            stdout.contains("MASQ Node 1.1.0 is available"),
+            true,
+            "stdout: '{}' doesn't contain 'MASQ Node 1.1.0 is available'",
+            stdout
+        );
+        assert_eq!(
+            handle.stderr_so_far(),
+            "".to_string(),
+            "stderr: '{}'",
+            stdout
+        );
+    }
+
     #[test]
     fn unexpected_broadcasts_are_ineffectual_but_dont_kill_the_handler() {
         let (factory, handle) = TestStreamFactory::new();
@@ -201,6 +309,7 @@ mod tests {
             running: true,
             values: vec![],
             errors: vec![],
+            instance_opt: None,
         }
         .tmb(0);
 
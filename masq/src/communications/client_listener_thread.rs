@@ -10,21 +10,23 @@ use websocket::receiver::Reader;
 use websocket::ws::receiver::Receiver as WsReceiver;
 use websocket::OwnedMessage;
 
-#[derive(Clone, Copy, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug)]
 pub enum ClientListenerError {
     Closed,
     Broken,
     Timeout,
     UnexpectedPacket,
+    Unauthorized(String),
 }
 
 impl ClientListenerError {
-    pub fn is_fatal(self) -> bool {
+    pub fn is_fatal(&self) -> bool {
         match self {
             ClientListenerError::Closed => true,
             ClientListenerError::Broken => true,
             ClientListenerError::Timeout => true,
             ClientListenerError::UnexpectedPacket => false,
+            ClientListenerError::Unauthorized(_) => true,
         }
     }
 }
@@ -33,6 +35,12 @@ pub struct ClientListener {
     signal_opt: Arc<Mutex<Option<Receiver<()>>>>,
 }
 
+impl Default for ClientListener {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ClientListener {
     pub fn new() -> Self {
         Self {
@@ -285,6 +293,10 @@ mod tests {
         assert_eq!(ClientListenerError::Broken.is_fatal(), true);
         assert_eq!(ClientListenerError::Timeout.is_fatal(), true);
         assert_eq!(ClientListenerError::UnexpectedPacket.is_fatal(), false);
+        assert_eq!(
+            ClientListenerError::Unauthorized(String::new()).is_fatal(),
+            true
+        );
     }
 
     fn wait_for_stop(listener: &ClientListener) {
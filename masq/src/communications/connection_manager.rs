@@ -7,12 +7,16 @@ use crate::communications::client_listener_thread::{ClientListener, ClientListen
 use crate::communications::node_conversation::{NodeConversation, NodeConversationTermination};
 use crossbeam_channel::{unbounded, RecvTimeoutError};
 use crossbeam_channel::{Receiver, RecvError, Sender};
-use masq_lib::messages::{CrashReason, FromMessageBody, ToMessageBody, UiNodeCrashedBroadcast};
+use masq_lib::messages::{
+    CrashReason, FromMessageBody, ToMessageBody, UiAuthenticateRequest, UiAuthenticateResponse,
+    UiMessageError, UiNodeCrashedBroadcast,
+};
 use masq_lib::messages::{UiRedirect, NODE_UI_PROTOCOL};
 use masq_lib::ui_gateway::{MessageBody, MessagePath};
 use masq_lib::ui_traffic_converter::UiTrafficConverter;
 use masq_lib::utils::localhost;
 use std::collections::{HashMap, HashSet};
+use std::io::ErrorKind;
 use std::net::TcpStream;
 use std::thread;
 use std::thread::JoinHandle;
@@ -21,7 +25,7 @@ use websocket::sender::Writer;
 use websocket::sync::Client;
 use websocket::ws::sender::Sender as WsSender;
 use websocket::OwnedMessage;
-use websocket::{ClientBuilder, WebSocketResult};
+use websocket::{ClientBuilder, WebSocketError, WebSocketResult};
 
 pub const COMPONENT_RESPONSE_TIMEOUT_MILLIS: u64 = 100;
 pub const REDIRECT_TIMEOUT_MILLIS: u64 = 500;
@@ -84,12 +88,18 @@ impl ConnectionManager {
     pub fn connect(
         &mut self,
         port: u16,
+        ui_password_opt: Option<String>,
         broadcast_handle: Box<dyn BroadcastHandle>,
         timeout_millis: u64,
     ) -> Result<(), ClientListenerError> {
         let (demand_tx, demand_rx) = unbounded();
         let (listener_to_manager_tx, listener_to_manager_rx) = unbounded();
-        let talker_half = make_client_listener(port, listener_to_manager_tx, timeout_millis)?;
+        let talker_half = make_client_listener(
+            port,
+            ui_password_opt.as_deref(),
+            listener_to_manager_tx,
+            timeout_millis,
+        )?;
         let (conversation_return_tx, conversation_return_rx) = unbounded();
         let (redirect_order_tx, redirect_order_rx) = unbounded();
         let (redirect_response_tx, redirect_response_rx) = unbounded();
@@ -104,6 +114,7 @@ impl ConnectionManager {
             active_port: Some(port),
             daemon_port: port,
             node_port: None,
+            ui_password_opt,
             conversations: HashMap::new(),
             conversations_waiting: HashSet::new(),
             next_context_id: 1,
@@ -154,6 +165,7 @@ impl ConnectionManager {
 
 fn make_client_listener(
     port: u16,
+    ui_password_opt: Option<&str>,
     listener_to_manager_tx: Sender<Result<MessageBody, ClientListenerError>>,
     timeout_millis: u64,
 ) -> Result<Writer<TcpStream>, ClientListenerError> {
@@ -165,16 +177,70 @@ fn make_client_listener(
         Err(RecvTimeoutError::Timeout) => return Err(ClientListenerError::Timeout),
         Ok(r) => r,
     };
-    let client = match result {
+    let mut client = match result {
         Ok(c) => c,
         Err(_) => return Err(ClientListenerError::Broken),
     };
+    if let Some(ui_password) = ui_password_opt {
+        authenticate(&mut client, ui_password, timeout_millis)?;
+    }
     let (listener_half, talker_half) = client.split().unwrap();
     let client_listener = ClientListener::new();
     client_listener.start(listener_half, listener_to_manager_tx);
     Ok(talker_half)
 }
 
+// Every connection to a Daemon or Node, including the ones made to follow a redirect or to fall
+// back, proves itself with the UI password before anything else goes over it
+fn authenticate(
+    client: &mut Client<TcpStream>,
+    ui_password: &str,
+    timeout_millis: u64,
+) -> Result<(), ClientListenerError> {
+    let request = UiAuthenticateRequest {
+        password: ui_password.to_string(),
+    }
+    .tmb(0);
+    if client
+        .send_message(&OwnedMessage::Text(UiTrafficConverter::new_marshal(
+            request,
+        )))
+        .is_err()
+    {
+        return Err(ClientListenerError::Broken);
+    }
+    let timeout = Some(Duration::from_millis(timeout_millis));
+    if client.stream_ref().set_read_timeout(timeout).is_err() {
+        return Err(ClientListenerError::Broken);
+    }
+    let received = client.recv_message();
+    if client.stream_ref().set_read_timeout(None).is_err() {
+        return Err(ClientListenerError::Broken);
+    }
+    let json = match received {
+        Ok(OwnedMessage::Text(json)) => json,
+        Ok(OwnedMessage::Close(_)) => return Err(ClientListenerError::Closed),
+        Ok(_) => return Err(ClientListenerError::UnexpectedPacket),
+        Err(WebSocketError::IoError(e))
+            if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::TimedOut =>
+        {
+            return Err(ClientListenerError::Timeout)
+        }
+        Err(_) => return Err(ClientListenerError::Broken),
+    };
+    let body = match UiTrafficConverter::new_unmarshal(&json) {
+        Ok(body) => body,
+        Err(_) => return Err(ClientListenerError::UnexpectedPacket),
+    };
+    match UiAuthenticateResponse::fmb(body) {
+        Ok(_) => Ok(()),
+        Err(UiMessageError::PayloadError(_, message)) => {
+            Err(ClientListenerError::Unauthorized(message))
+        }
+        Err(_) => Err(ClientListenerError::UnexpectedPacket),
+    }
+}
+
 fn connect_insecure_timeout(
     mut builder: ClientBuilder<'static>,
     timeout_millis: u64,
@@ -191,6 +257,7 @@ struct CmsInner {
     active_port: Option<u16>,
     daemon_port: u16,
     node_port: Option<u16>,
+    ui_password_opt: Option<String>,
     conversations: HashMap<u64, Sender<Result<MessageBody, NodeConversationTermination>>>,
     conversations_waiting: HashSet<u64>,
     next_context_id: u64,
@@ -222,6 +289,10 @@ impl ConnectionManagerThread {
                 let msg_body = UiNodeCrashedBroadcast {
                     process_id: 0,
                     crash_reason: CrashReason::DaemonCrashed,
+                    exit_code_opt: None,
+                    last_log_lines: vec![],
+                    restart_delay_secs_opt: None,
+                    instance_opt: None,
                 }
                 .tmb(0);
                 inner.broadcast_handle.send(msg_body);
@@ -377,6 +448,7 @@ impl ConnectionManagerThread {
         let (listener_to_manager_tx, listener_to_manager_rx) = unbounded();
         let talker_half = match make_client_listener(
             redirect_order.port,
+            inner.ui_password_opt.as_deref(),
             listener_to_manager_tx,
             redirect_order.timeout_millis,
         ) {
@@ -448,6 +520,7 @@ impl ConnectionManagerThread {
         inner.listener_to_manager_rx = listener_to_manager_rx;
         match make_client_listener(
             inner.active_port.expect("Active port disappeared!"),
+            inner.ui_password_opt.as_deref(),
             listener_to_manager_tx,
             FALLBACK_TIMEOUT_MILLIS,
         ) {
@@ -592,7 +665,7 @@ mod tests {
         thread::sleep(Duration::from_millis(500)); // let the server get started
         let mut subject = ConnectionManager::new();
         subject
-            .connect(port, Box::new(BroadcastHandleMock::new()), 1000)
+            .connect(port, None, Box::new(BroadcastHandleMock::new()), 1000)
             .unwrap();
         (subject, stop_handle)
     }
@@ -641,7 +714,7 @@ mod tests {
                 .transact(UiShutdownRequest {}.tmb(0), 1001)
                 .unwrap();
             let response2 = conversation1
-                .transact(UiStartOrder {}.tmb(0), 1002)
+                .transact(UiStartOrder { instance_opt: None }.tmb(0), 1002)
                 .unwrap();
             (response1, response2)
         });
@@ -650,7 +723,7 @@ mod tests {
                 .transact(UiShutdownRequest {}.tmb(0), 1003)
                 .unwrap();
             let response2 = conversation2
-                .transact(UiStartOrder {}.tmb(0), 1004)
+                .transact(UiStartOrder { instance_opt: None }.tmb(0), 1004)
                 .unwrap();
             (response1, response2)
         });
@@ -849,6 +922,7 @@ mod tests {
             running: false,
             values: vec![],
             errors: vec![],
+            instance_opt: None,
         }
         .tmb(4);
         let daemon = MockWebSocketsServer::new(daemon_port)
@@ -880,13 +954,55 @@ mod tests {
         let _ = ConnectionManagerThread::handle_outgoing_message_body(
             inner,
             Ok(OutgoingMessageType::ConversationMessage(
-                UiSetupRequest { values: vec![] }.tmb(4),
+                UiSetupRequest {
+                    values: vec![],
+                    instance_opt: None,
+                }
+                .tmb(4),
             )),
         );
         let mut outgoing_messages = stop_handle.stop();
         assert_eq!(
-            outgoing_messages.remove(0),
-            Ok(UiSetupRequest { values: vec![] }.tmb(4))
+            UiSetupRequest::fmb(outgoing_messages.remove(0).unwrap()).unwrap(),
+            (
+                UiSetupRequest {
+                    values: vec![],
+                    instance_opt: None
+                },
+                4
+            )
+        );
+    }
+
+    #[test]
+    fn authenticates_again_when_falling_back_to_the_daemon() {
+        let daemon_port = find_free_port();
+        let daemon = MockWebSocketsServer::new(daemon_port).queue_response(
+            UiAuthenticateResponse {
+                permission: "full".to_string(),
+            }
+            .tmb(0),
+        );
+        let stop_handle = daemon.start();
+        let node_port = find_free_port();
+        let mut inner = make_inner();
+        inner.active_port = Some(node_port);
+        inner.daemon_port = daemon_port;
+        inner.node_port = Some(node_port);
+        inner.ui_password_opt = Some("booga".to_string());
+
+        let inner = ConnectionManagerThread::handle_incoming_message_body(inner, Err(RecvError));
+
+        assert_eq!(inner.active_port, Some(daemon_port));
+        let mut outgoing_messages = stop_handle.stop();
+        assert_eq!(
+            UiAuthenticateRequest::fmb(outgoing_messages.remove(0).unwrap()).unwrap(),
+            (
+                UiAuthenticateRequest {
+                    password: "booga".to_string()
+                },
+                0
+            )
         );
     }
 
@@ -1037,6 +1153,7 @@ mod tests {
             running: false,
             values: vec![],
             errors: vec![],
+            instance_opt: None,
         }
         .tmb(4);
         let daemon = MockWebSocketsServer::new(daemon_port)
@@ -1071,13 +1188,23 @@ mod tests {
         let _ = ConnectionManagerThread::handle_outgoing_message_body(
             inner,
             Ok(OutgoingMessageType::ConversationMessage(
-                UiSetupRequest { values: vec![] }.tmb(4),
+                UiSetupRequest {
+                    values: vec![],
+                    instance_opt: None,
+                }
+                .tmb(4),
             )),
         );
         let mut outgoing_messages = stop_handle.stop();
         assert_eq!(
-            outgoing_messages.remove(0),
-            Ok(UiSetupRequest { values: vec![] }.tmb(4))
+            UiSetupRequest::fmb(outgoing_messages.remove(0).unwrap()).unwrap(),
+            (
+                UiSetupRequest {
+                    values: vec![],
+                    instance_opt: None
+                },
+                4
+            )
         );
     }
 
@@ -1111,6 +1238,7 @@ mod tests {
             running: false,
             values: vec![],
             errors: vec![],
+            instance_opt: None,
         }
         .tmb(0);
         let (conversation_tx, conversation_rx) = unbounded();
@@ -1174,7 +1302,7 @@ mod tests {
         let broadcast_handler = BroadcastHandleMock::new().send_params(&send_params_arc);
         let mut subject = ConnectionManager::new();
         subject
-            .connect(daemon_port, Box::new(broadcast_handler), 1000)
+            .connect(daemon_port, None, Box::new(broadcast_handler), 1000)
             .unwrap();
         let conversation = subject.start_conversation();
 
@@ -1206,12 +1334,96 @@ mod tests {
         daemon_stop_handle.stop();
     }
 
+    #[test]
+    fn authenticates_with_the_node_too_when_following_a_redirect() {
+        let node_port = find_free_port();
+        let node_server = MockWebSocketsServer::new(node_port)
+            .queue_response(
+                UiAuthenticateResponse {
+                    permission: "full".to_string(),
+                }
+                .tmb(0),
+            )
+            .queue_response(
+                UiFinancialsResponse {
+                    payables: vec![],
+                    total_payable: 21,
+                    receivables: vec![],
+                    total_receivable: 32,
+                }
+                .tmb(1),
+            );
+        let node_stop_handle = node_server.start();
+        let daemon_port = find_free_port();
+        let daemon_server = MockWebSocketsServer::new(daemon_port)
+            .queue_response(
+                UiAuthenticateResponse {
+                    permission: "full".to_string(),
+                }
+                .tmb(0),
+            )
+            .queue_response(
+                UiRedirect {
+                    port: node_port,
+                    opcode: "financials".to_string(),
+                    context_id: Some(1),
+                    payload: r#"{"payableMinimumAmount":12,"payableMaximumAge":23,"receivableMinimumAmount":34,"receivableMaximumAge":45}"#.to_string(),
+                }
+                .tmb(0),
+            );
+        let daemon_stop_handle = daemon_server.start();
+        let request = UiFinancialsRequest {
+            payable_minimum_amount: 12,
+            payable_maximum_age: 23,
+            receivable_minimum_amount: 34,
+            receivable_maximum_age: 45,
+        }
+        .tmb(1);
+        let mut subject = ConnectionManager::new();
+        subject
+            .connect(
+                daemon_port,
+                Some("booga".to_string()),
+                Box::new(BroadcastHandleMock::new()),
+                1000,
+            )
+            .unwrap();
+        let conversation = subject.start_conversation();
+
+        let result = conversation.transact(request, 1000).unwrap();
+
+        assert_eq!(UiFinancialsResponse::fmb(result).unwrap().1, 1);
+        let expected_authentication = (
+            UiAuthenticateRequest {
+                password: "booga".to_string(),
+            },
+            0,
+        );
+        let node_requests = node_stop_handle.stop();
+        assert_eq!(
+            UiAuthenticateRequest::fmb(node_requests[0].clone().unwrap()).unwrap(),
+            expected_authentication
+        );
+        assert_eq!(
+            UiFinancialsRequest::fmb(node_requests[1].clone().unwrap())
+                .unwrap()
+                .1,
+            1
+        );
+        let daemon_requests = daemon_stop_handle.stop();
+        assert_eq!(
+            UiAuthenticateRequest::fmb(daemon_requests[0].clone().unwrap()).unwrap(),
+            expected_authentication
+        );
+    }
+
     #[test]
     fn handles_response_to_nonexistent_conversation() {
         let incoming_message = UiSetupResponse {
             running: false,
             values: vec![],
             errors: vec![],
+            instance_opt: None,
         }
         .tmb(3);
         let (conversation_tx, conversation_rx) = unbounded();
@@ -1234,6 +1446,7 @@ mod tests {
             running: false,
             values: vec![],
             errors: vec![],
+            instance_opt: None,
         }
         .tmb(4);
         let (conversation_tx, _) = unbounded();
@@ -1334,7 +1547,11 @@ mod tests {
         inner = ConnectionManagerThread::handle_outgoing_message_body(
             inner,
             Ok(OutgoingMessageType::ConversationMessage(
-                UiSetupRequest { values: vec![] }.tmb(2),
+                UiSetupRequest {
+                    values: vec![],
+                    instance_opt: None,
+                }
+                .tmb(2),
             )),
         );
 
@@ -1363,7 +1580,11 @@ mod tests {
         inner = ConnectionManagerThread::handle_outgoing_message_body(
             inner,
             Ok(OutgoingMessageType::ConversationMessage(
-                UiSetupRequest { values: vec![] }.tmb(42),
+                UiSetupRequest {
+                    values: vec![],
+                    instance_opt: None,
+                }
+                .tmb(42),
             )),
         );
 
@@ -1434,7 +1655,7 @@ mod tests {
         let mut subject = ConnectionManager::new();
         thread::sleep(Duration::from_millis(500)); // let the server get started
         subject
-            .connect(port, Box::new(BroadcastHandleMock::new()), 1000)
+            .connect(port, None, Box::new(BroadcastHandleMock::new()), 1000)
             .unwrap();
         let conversation1 = subject.start_conversation();
         let conversation2 = subject.start_conversation();
@@ -1460,6 +1681,7 @@ mod tests {
             active_port: Some(0),
             daemon_port: 0,
             node_port: None,
+            ui_password_opt: None,
             conversations: HashMap::new(),
             conversations_waiting: HashSet::new(),
             next_context_id: 0,
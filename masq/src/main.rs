@@ -5,9 +5,11 @@ use masq_cli_lib::command_factory::{CommandFactory, CommandFactoryReal};
 use masq_cli_lib::command_processor::{
     CommandProcessor, CommandProcessorFactory, CommandProcessorFactoryReal,
 };
+use masq_cli_lib::commands::commands_common::CommandError;
 use masq_cli_lib::communications::broadcast_handler::StreamFactoryReal;
 use masq_lib::command;
 use masq_lib::command::{Command, StdStreams};
+use masq_lib::messages::AUTHENTICATION_ERROR;
 use std::io;
 use std::io::{BufRead, BufReader};
 
@@ -37,6 +39,15 @@ impl command::Command for Main {
             .make(Box::new(broadcast_stream_factory), args)
         {
             Ok(processor) => processor,
+            Err(CommandError::Payload(AUTHENTICATION_ERROR, message)) => {
+                writeln!(
+                    streams.stderr,
+                    "Daemon or Node didn't accept the UI password: {}",
+                    message
+                )
+                .expect("writeln! failed");
+                return 1;
+            }
             Err(e) => {
                 writeln!(streams.stderr, "Can't connect to Daemon or Node ({:?}). Probably this means the Daemon isn't running.", e).expect ("writeln! failed");
                 return 1;
@@ -52,6 +63,8 @@ impl command::Command for Main {
             None => self.go_interactive(&mut *processor, streams),
         };
         processor.close();
+        #[cfg(feature = "clipboard")]
+        masq_lib::clipboard::wait_for_clipboard_clears();
         result
     }
 }
@@ -73,6 +86,9 @@ impl Main {
                 return Some(args_vec.into_iter().skip(idx).collect());
             }
         }
+        if args_vec.iter().any(|arg| arg == "--tui") {
+            return Some(vec!["tui".to_string()]);
+        }
         None
     }
 
@@ -174,7 +190,6 @@ mod tests {
     use masq_cli_lib::command_context::ContextError::Other;
     use masq_cli_lib::command_factory::CommandFactoryError;
     use masq_cli_lib::commands::commands_common;
-    use masq_cli_lib::commands::commands_common::CommandError;
     use masq_cli_lib::commands::commands_common::CommandError::Transmission;
     use masq_cli_lib::test_utils::mocks::{
         CommandContextMock, CommandFactoryMock, CommandProcessorFactoryMock, CommandProcessorMock,
@@ -296,6 +311,25 @@ mod tests {
         }
     }
 
+    #[test]
+    fn tui_flag_stands_in_for_the_tui_command() {
+        let args = |args: &[&str]| {
+            args.iter()
+                .map(|arg| arg.to_string())
+                .collect::<Vec<String>>()
+        };
+
+        assert_eq!(
+            Main::extract_subcommand(&args(&["masq", "--ui-port", "5333", "--tui"])),
+            Some(args(&["tui"]))
+        );
+        assert_eq!(
+            Main::extract_subcommand(&args(&["masq", "--ui-port", "5333", "streams"])),
+            Some(args(&["streams"]))
+        );
+        assert_eq!(Main::extract_subcommand(&args(&["masq"])), None);
+    }
+
     #[test]
     fn interactive_mode_works_when_everything_is_copacetic() {
         let make_params_arc = Arc::new(Mutex::new(vec![]));
@@ -654,4 +688,30 @@ mod tests {
             "Can't connect to Daemon or Node (ConnectionProblem(\"booga\")). Probably this means the Daemon isn't running.\n".to_string()
         );
     }
+
+    #[test]
+    fn go_says_so_when_the_ui_password_isnt_accepted() {
+        let processor_factory =
+            CommandProcessorFactoryMock::new().make_result(Err(CommandError::Payload(
+                AUTHENTICATION_ERROR,
+                "Wrong password; 4 more tries before 127.0.0.1 is locked out".to_string(),
+            )));
+        let mut subject = Main {
+            command_factory: Box::new(CommandFactoryMock::new()),
+            processor_factory: Box::new(processor_factory),
+        };
+        let mut stream_holder = FakeStreamHolder::new();
+
+        let result = subject.go(
+            &mut stream_holder.streams(),
+            &["command".to_string(), "subcommand".to_string()],
+        );
+
+        assert_eq!(result, 1);
+        assert_eq!(stream_holder.stdout.get_string(), "".to_string());
+        assert_eq!(
+            stream_holder.stderr.get_string(),
+            "Daemon or Node didn't accept the UI password: Wrong password; 4 more tries before 127.0.0.1 is locked out\n".to_string()
+        );
+    }
 }
@@ -17,10 +17,28 @@ impl CrashNotifier {
         }
         writeln!(
             stdout,
-            "\nThe Node running as process {} terminated{}\nThe Daemon is once more accepting setup changes.\n",
+            "\nThe Node running as process {} terminated{}",
             response.process_id,
-            Self::dress_message (response.crash_reason)
+            Self::dress_message(response.crash_reason)
         )
+        .expect("writeln! failed");
+        if !response.last_log_lines.is_empty() {
+            writeln!(
+                stdout,
+                "The end of its log:\n------\n{}\n------",
+                response.last_log_lines.join("\n")
+            )
+            .expect("writeln! failed");
+        }
+        if let Some(delay_secs) = response.restart_delay_secs_opt {
+            writeln!(
+                stdout,
+                "The Daemon will try to restart it in {} seconds.",
+                delay_secs
+            )
+            .expect("writeln! failed");
+        }
+        writeln!(stdout, "The Daemon is once more accepting setup changes.\n")
             .expect("writeln! failed");
         write!(stdout, "masq> ").expect("write! failed");
         stdout.flush().expect("flush failed");
@@ -81,6 +99,10 @@ mod tests {
         let msg = UiNodeCrashedBroadcast {
             process_id: 12345,
             crash_reason: CrashReason::ChildWaitFailure("Couldn't wait".to_string()),
+            exit_code_opt: None,
+            last_log_lines: vec![],
+            restart_delay_secs_opt: None,
+            instance_opt: None,
         }
         .tmb(0);
 
@@ -98,6 +120,10 @@ mod tests {
         let msg = UiNodeCrashedBroadcast {
             process_id: 12345,
             crash_reason: CrashReason::Unrecognized("Just...failed!\n\n".to_string()),
+            exit_code_opt: None,
+            last_log_lines: vec![],
+            restart_delay_secs_opt: None,
+            instance_opt: None,
         }
         .tmb(0);
 
@@ -115,6 +141,10 @@ mod tests {
         let msg = UiNodeCrashedBroadcast {
             process_id: 12345,
             crash_reason: CrashReason::NoInformation,
+            exit_code_opt: None,
+            last_log_lines: vec![],
+            restart_delay_secs_opt: None,
+            instance_opt: None,
         }
         .tmb(0);
 
@@ -124,6 +154,27 @@ mod tests {
         assert_eq!(stderr.get_string(), "".to_string());
     }
 
+    #[test]
+    pub fn shows_the_end_of_the_log_and_the_coming_restart() {
+        running_test();
+        let mut stdout = ByteArrayWriter::new();
+        let mut stderr = ByteArrayWriter::new();
+        let msg = UiNodeCrashedBroadcast {
+            process_id: 12345,
+            crash_reason: CrashReason::NoInformation,
+            exit_code_opt: Some(101),
+            last_log_lines: vec!["Penultimate line".to_string(), "Last line".to_string()],
+            restart_delay_secs_opt: Some(10),
+            instance_opt: Some("default".to_string()),
+        }
+        .tmb(0);
+
+        CrashNotifier::handle_broadcast(msg, &mut stdout, &mut stderr);
+
+        assert_eq! (stdout.get_string(), "\nThe Node running as process 12345 terminated.\nThe end of its log:\n------\nPenultimate line\nLast line\n------\nThe Daemon will try to restart it in 10 seconds.\nThe Daemon is once more accepting setup changes.\n\nmasq> ".to_string());
+        assert_eq!(stderr.get_string(), "".to_string());
+    }
+
     #[test]
     #[should_panic(expected = "1: The Daemon is no longer running; masq is terminating.")]
     pub fn handles_daemon_crash() {
@@ -133,6 +184,10 @@ mod tests {
         let msg = UiNodeCrashedBroadcast {
             process_id: 12345,
             crash_reason: CrashReason::DaemonCrashed,
+            exit_code_opt: None,
+            last_log_lines: vec![],
+            restart_delay_secs_opt: None,
+            instance_opt: None,
         }
         .tmb(0);
 
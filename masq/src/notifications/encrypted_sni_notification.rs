@@ -0,0 +1,69 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+
+use masq_lib::messages::FromMessageBody;
+use masq_lib::messages::UiEncryptedSniBroadcast;
+use masq_lib::ui_gateway::MessageBody;
+use std::io::Write;
+
+pub struct EncryptedSniNotifier {}
+
+impl EncryptedSniNotifier {
+    pub fn handle_broadcast(msg: MessageBody, stdout: &mut dyn Write, _stderr: &mut dyn Write) {
+        let (broadcast, _) = UiEncryptedSniBroadcast::fmb(msg.clone())
+            .unwrap_or_else(|_| panic!("Bad UiEncryptedSniBroadcast:\n{:?}", msg));
+        writeln!(
+            stdout,
+            "\nTLS connection {} from {} hides the name of its server. Send it somewhere with\n    encrypted-sni-host {} <HOST>\nor refuse it with\n    encrypted-sni-host {}\n",
+            broadcast.connection_id,
+            broadcast.client_address,
+            broadcast.connection_id,
+            broadcast.connection_id
+        )
+        .expect("writeln! failed");
+        write!(stdout, "masq> ").expect("write! failed");
+        stdout.flush().expect("flush failed");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use masq_lib::messages::ToMessageBody;
+    use masq_lib::test_utils::fake_stream_holder::ByteArrayWriter;
+    use masq_lib::ui_gateway::MessagePath;
+    use masq_lib::utils::running_test;
+
+    #[test]
+    #[should_panic(
+        expected = "Bad UiEncryptedSniBroadcast:\nMessageBody { opcode: \"booga\", path: Conversation(1234), payload: Ok(\"booga\") }"
+    )]
+    pub fn must_have_real_ui_encrypted_sni_broadcast() {
+        running_test();
+        let mut stdout = ByteArrayWriter::new();
+        let mut stderr = ByteArrayWriter::new();
+        let bad_msg = MessageBody {
+            opcode: "booga".to_string(),
+            path: MessagePath::Conversation(1234),
+            payload: Ok("booga".to_string()),
+        };
+
+        EncryptedSniNotifier::handle_broadcast(bad_msg, &mut stdout, &mut stderr)
+    }
+
+    #[test]
+    pub fn tells_how_to_name_the_server() {
+        running_test();
+        let mut stdout = ByteArrayWriter::new();
+        let mut stderr = ByteArrayWriter::new();
+        let msg = UiEncryptedSniBroadcast {
+            connection_id: 12,
+            client_address: "127.0.0.1:5678".to_string(),
+        }
+        .tmb(0);
+
+        EncryptedSniNotifier::handle_broadcast(msg, &mut stdout, &mut stderr);
+
+        assert_eq!(stdout.get_string(), "\nTLS connection 12 from 127.0.0.1:5678 hides the name of its server. Send it somewhere with\n    encrypted-sni-host 12 <HOST>\nor refuse it with\n    encrypted-sni-host 12\n\nmasq> ".to_string());
+        assert_eq!(stderr.get_string(), "".to_string());
+    }
+}
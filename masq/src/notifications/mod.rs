@@ -1,3 +1,6 @@
 // Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
 
 pub mod crashed_notification;
+pub mod encrypted_sni_notification;
+pub mod restarted_notification;
+pub mod update_notification;
@@ -0,0 +1,68 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+
+use masq_lib::messages::FromMessageBody;
+use masq_lib::messages::UiNodeRestartedBroadcast;
+use masq_lib::ui_gateway::MessageBody;
+use std::io::Write;
+
+pub struct RestartNotifier {}
+
+impl RestartNotifier {
+    pub fn handle_broadcast(msg: MessageBody, stdout: &mut dyn Write, _stderr: &mut dyn Write) {
+        let (broadcast, _) = UiNodeRestartedBroadcast::fmb(msg.clone())
+            .unwrap_or_else(|_| panic!("Bad UiNodeRestartedBroadcast:\n{:?}", msg));
+        writeln!(
+            stdout,
+            "\nThe Daemon restarted the crashed Node (attempt {}). It's running as process {} and\n    listening for UIs on port {}.\n",
+            broadcast.attempt, broadcast.new_process_id, broadcast.redirect_ui_port
+        )
+        .expect("writeln! failed");
+        write!(stdout, "masq> ").expect("write! failed");
+        stdout.flush().expect("flush failed");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use masq_lib::messages::ToMessageBody;
+    use masq_lib::test_utils::fake_stream_holder::ByteArrayWriter;
+    use masq_lib::ui_gateway::MessagePath;
+    use masq_lib::utils::running_test;
+
+    #[test]
+    #[should_panic(
+        expected = "Bad UiNodeRestartedBroadcast:\nMessageBody { opcode: \"booga\", path: Conversation(1234), payload: Ok(\"booga\") }"
+    )]
+    pub fn must_have_real_ui_node_restarted_broadcast() {
+        running_test();
+        let mut stdout = ByteArrayWriter::new();
+        let mut stderr = ByteArrayWriter::new();
+        let bad_msg = MessageBody {
+            opcode: "booga".to_string(),
+            path: MessagePath::Conversation(1234),
+            payload: Ok("booga".to_string()),
+        };
+
+        RestartNotifier::handle_broadcast(bad_msg, &mut stdout, &mut stderr)
+    }
+
+    #[test]
+    pub fn tells_where_the_restarted_node_is() {
+        running_test();
+        let mut stdout = ByteArrayWriter::new();
+        let mut stderr = ByteArrayWriter::new();
+        let msg = UiNodeRestartedBroadcast {
+            new_process_id: 2345,
+            redirect_ui_port: 5334,
+            attempt: 2,
+            instance_opt: None,
+        }
+        .tmb(0);
+
+        RestartNotifier::handle_broadcast(msg, &mut stdout, &mut stderr);
+
+        assert_eq!(stdout.get_string(), "\nThe Daemon restarted the crashed Node (attempt 2). It's running as process 2345 and\n    listening for UIs on port 5334.\n\nmasq> ".to_string());
+        assert_eq!(stderr.get_string(), "".to_string());
+    }
+}
@@ -0,0 +1,75 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+
+use masq_lib::messages::FromMessageBody;
+use masq_lib::messages::UiUpdateAvailableBroadcast;
+use masq_lib::ui_gateway::MessageBody;
+use std::io::Write;
+
+pub struct UpdateNotifier {}
+
+impl UpdateNotifier {
+    pub fn handle_broadcast(msg: MessageBody, stdout: &mut dyn Write, _stderr: &mut dyn Write) {
+        let (broadcast, _) = UiUpdateAvailableBroadcast::fmb(msg.clone())
+            .unwrap_or_else(|_| panic!("Bad UiUpdateAvailableBroadcast:\n{:?}", msg));
+        writeln!(
+            stdout,
+            "\nMASQ Node {} is available{}; you're running {}. Release notes:\n    {}\n",
+            broadcast.latest_version,
+            if broadcast.security {
+                ", with security fixes"
+            } else {
+                ""
+            },
+            broadcast.current_version,
+            broadcast.release_notes_url
+        )
+        .expect("writeln! failed");
+        write!(stdout, "masq> ").expect("write! failed");
+        stdout.flush().expect("flush failed");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use masq_lib::messages::ToMessageBody;
+    use masq_lib::test_utils::fake_stream_holder::ByteArrayWriter;
+    use masq_lib::ui_gateway::MessagePath;
+    use masq_lib::utils::running_test;
+
+    #[test]
+    #[should_panic(
+        expected = "Bad UiUpdateAvailableBroadcast:\nMessageBody { opcode: \"booga\", path: Conversation(1234), payload: Ok(\"booga\") }"
+    )]
+    pub fn must_have_real_ui_update_available_broadcast() {
+        running_test();
+        let mut stdout = ByteArrayWriter::new();
+        let mut stderr = ByteArrayWriter::new();
+        let bad_msg = MessageBody {
+            opcode: "booga".to_string(),
+            path: MessagePath::Conversation(1234),
+            payload: Ok("booga".to_string()),
+        };
+
+        UpdateNotifier::handle_broadcast(bad_msg, &mut stdout, &mut stderr)
+    }
+
+    #[test]
+    pub fn tells_where_the_release_notes_are() {
+        running_test();
+        let mut stdout = ByteArrayWriter::new();
+        let mut stderr = ByteArrayWriter::new();
+        let msg = UiUpdateAvailableBroadcast {
+            current_version: "1.0.0".to_string(),
+            latest_version: "1.1.0".to_string(),
+            release_notes_url: "https://releases.example.com/1.1.0".to_string(),
+            security: true,
+        }
+        .tmb(0);
+
+        UpdateNotifier::handle_broadcast(msg, &mut stdout, &mut stderr);
+
+        assert_eq!(stdout.get_string(), "\nMASQ Node 1.1.0 is available, with security fixes; you're running 1.0.0. Release notes:\n    https://releases.example.com/1.1.0\n\nmasq> ".to_string());
+        assert_eq!(stderr.get_string(), "".to_string());
+    }
+}
@@ -1,9 +1,16 @@
 // Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use crate::commands::application_rules_command::application_rules_subcommand;
 use crate::commands::crash_command::crash_subcommand;
 use crate::commands::descriptor_command::descriptor_subcommand;
+use crate::commands::encrypted_sni_host_command::encrypted_sni_host_subcommand;
+use crate::commands::neighborhood_graph_command::neighborhood_graph_subcommand;
 use crate::commands::setup_command::setup_subcommand;
 use crate::commands::shutdown_command::shutdown_subcommand;
+use crate::commands::speed_test_command::speed_test_subcommand;
+use crate::commands::split_tunnel_rules_command::split_tunnel_rules_subcommand;
 use crate::commands::start_command::start_subcommand;
+use crate::commands::streams_command::streams_subcommand;
+use crate::commands::tui_command::tui_subcommand;
 use clap::{App, AppSettings, Arg};
 use lazy_static::lazy_static;
 use masq_lib::constants::{DEFAULT_UI_PORT, HIGHEST_USABLE_PORT, LOWEST_USABLE_INSECURE_PORT};
@@ -42,11 +49,33 @@ pub fn app() -> App<'static, 'static> {
                 .validator(validate_ui_port)
                 .help(UI_PORT_HELP.as_str()),
         )
+        .arg(
+            Arg::with_name("ui-password")
+                .long("ui-password")
+                .value_name("UI-PASSWORD")
+                .takes_value(true)
+                .env("MASQ_UI_PASSWORD")
+                .hide_env_values(true)
+                .help("If the Daemon or Node was started with --ui-password, give that password (or its --ui-read-only-password) \
+                       here or in the MASQ_UI_PASSWORD environment variable; masq presents it every time it connects"),
+        )
+        .arg(
+            Arg::with_name("tui")
+                .long("tui")
+                .help("Instead of taking commands, shows the running Node's status on a screen that refreshes itself; the same as the tui command"),
+        )
+        .subcommand(application_rules_subcommand())
         .subcommand(crash_subcommand())
         .subcommand(descriptor_subcommand())
+        .subcommand(encrypted_sni_host_subcommand())
+        .subcommand(neighborhood_graph_subcommand())
         .subcommand(setup_subcommand())
         .subcommand(start_subcommand())
         .subcommand(shutdown_subcommand())
+        .subcommand(speed_test_subcommand())
+        .subcommand(split_tunnel_rules_subcommand())
+        .subcommand(streams_subcommand())
+        .subcommand(tui_subcommand())
 }
 
 fn validate_ui_port(port: String) -> Result<(), String> {
@@ -0,0 +1,80 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+
+use crate::utils::DaemonProcess;
+use crate::utils::MasqProcess;
+use masq_lib::utils::find_free_port;
+use std::thread;
+use std::time::Duration;
+
+mod utils;
+
+#[test]
+#[ignore]
+fn masq_authenticates_with_a_protected_daemon_integration() {
+    let port = find_free_port();
+    let port_string = format!("{}", port);
+    let daemon_handle = DaemonProcess::new().start_with_args(port, vec!["--ui-password", "booga"]);
+
+    thread::sleep(Duration::from_millis(500));
+
+    let masq_handle =
+        MasqProcess::new().start_noninteractive(vec!["--ui-port", &port_string, "setup"]);
+
+    let (stdout, stderr, exit_code) = masq_handle.stop();
+
+    assert_eq!(&stdout, "", "{}", stdout);
+    assert_eq!(
+        stderr.contains("Authenticate before sending 'setup'"),
+        true,
+        "{}",
+        stderr
+    );
+    assert_eq!(exit_code, 1);
+
+    let masq_handle = MasqProcess::new().start_noninteractive(vec![
+        "--ui-port",
+        &port_string,
+        "--ui-password",
+        "wrong",
+        "setup",
+    ]);
+
+    let (stdout, stderr, exit_code) = masq_handle.stop();
+
+    assert_eq!(&stdout, "", "{}", stdout);
+    assert_eq!(
+        stderr
+            .contains("Daemon or Node didn't accept the UI password: Wrong password; 4 more tries"),
+        true,
+        "{}",
+        stderr
+    );
+    assert_eq!(exit_code, 1);
+
+    let masq_handle = MasqProcess::new().start_noninteractive(vec![
+        "--ui-port",
+        &port_string,
+        "--ui-password",
+        "booga",
+        "setup",
+    ]);
+
+    let (stdout, stderr, exit_code) = masq_handle.stop();
+
+    assert_eq!(&stderr, "", "{}", stderr);
+    assert_eq!(stdout.contains("neighborhood-mode"), true, "{}", stdout);
+    assert_eq!(exit_code, 0);
+
+    let masq_handle = MasqProcess::new().start_noninteractive_with_env(
+        vec!["--ui-port", &port_string, "setup"],
+        vec![("MASQ_UI_PASSWORD", "booga")],
+    );
+
+    let (stdout, stderr, exit_code) = masq_handle.stop();
+
+    assert_eq!(&stderr, "", "{}", stderr);
+    assert_eq!(stdout.contains("neighborhood-mode"), true, "{}", stdout);
+    assert_eq!(exit_code, 0);
+
+    daemon_handle.kill();
+}
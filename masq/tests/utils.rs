@@ -16,12 +16,17 @@ impl DaemonProcess {
     }
 
     pub fn start(self, port: u16) -> StopHandle {
+        self.start_with_args(port, vec![])
+    }
+
+    pub fn start_with_args(self, port: u16, extra_args: Vec<&str>) -> StopHandle {
         let executable = executable_path(executable_name("MASQNode"));
-        let args = vec![
+        let mut args = vec![
             "--ui-port".to_string(),
             format!("{}", port),
             "--initialization".to_string(),
         ];
+        args.extend(extra_args.into_iter().map(|arg| arg.to_string()));
         eprintln!(
             "About to start Daemon at '{:?}' with args {:?}",
             executable, args
@@ -45,8 +50,16 @@ impl MasqProcess {
     }
 
     pub fn start_noninteractive(self, params: Vec<&str>) -> StopHandle {
+        self.start_noninteractive_with_env(params, vec![])
+    }
+
+    pub fn start_noninteractive_with_env(
+        self,
+        params: Vec<&str>,
+        env: Vec<(&str, &str)>,
+    ) -> StopHandle {
         let mut command = Command::new(executable_path(executable_name("masq")));
-        let command = command.args(params);
+        let command = command.args(params).envs(env);
         let child = child_from_command(command);
         StopHandle {
             name: "masq".to_string(),
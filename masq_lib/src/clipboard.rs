@@ -0,0 +1,189 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use lazy_static::lazy_static;
+use std::fmt::Debug;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+// Long enough to paste what was copied; short enough that it isn't still there for the next paste
+pub const CLIPBOARD_TIMEOUT: Duration = Duration::from_secs(30);
+
+lazy_static! {
+    static ref PENDING_CLEARS: Mutex<Vec<JoinHandle<()>>> = Mutex::new(vec![]);
+}
+
+pub trait Clipboard: Send + Sync + Debug {
+    fn set(&self, text: &str) -> Result<(), String>;
+    fn get(&self) -> Result<String, String>;
+}
+
+// Drives the tools the system already has for its clipboard, so that no window system has to be
+// linked into the binaries
+#[derive(Debug, Default)]
+pub struct SystemClipboard {}
+
+impl Clipboard for SystemClipboard {
+    fn set(&self, text: &str) -> Result<(), String> {
+        let (program, args) = copy_command();
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("Couldn't run {}: {}", program, e))?;
+        child
+            .stdin
+            .take()
+            .expect("Clipboard tool has no stdin")
+            .write_all(text.as_bytes())
+            .map_err(|e| format!("Couldn't write to {}: {}", program, e))?;
+        match child.wait() {
+            Ok(status) if status.success() => Ok(()),
+            Ok(status) => Err(format!("{} failed: {}", program, status)),
+            Err(e) => Err(format!("Couldn't run {}: {}", program, e)),
+        }
+    }
+
+    fn get(&self) -> Result<String, String> {
+        let (program, args) = paste_command();
+        let output = Command::new(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .map_err(|e| format!("Couldn't run {}: {}", program, e))?;
+        if output.status.success() {
+            Ok(String::from_utf8_lossy(&output.stdout).to_string())
+        } else {
+            Err(format!("{} failed: {}", program, output.status))
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn copy_command() -> (&'static str, &'static [&'static str]) {
+    ("pbcopy", &[])
+}
+
+#[cfg(target_os = "macos")]
+fn paste_command() -> (&'static str, &'static [&'static str]) {
+    ("pbpaste", &[])
+}
+
+#[cfg(target_os = "windows")]
+fn copy_command() -> (&'static str, &'static [&'static str]) {
+    ("clip", &[])
+}
+
+#[cfg(target_os = "windows")]
+fn paste_command() -> (&'static str, &'static [&'static str]) {
+    ("powershell", &["-NoProfile", "-Command", "Get-Clipboard"])
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn copy_command() -> (&'static str, &'static [&'static str]) {
+    if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        ("wl-copy", &[])
+    } else {
+        ("xclip", &["-selection", "clipboard"])
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn paste_command() -> (&'static str, &'static [&'static str]) {
+    if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        ("wl-paste", &["--no-newline"])
+    } else {
+        ("xclip", &["-selection", "clipboard", "-o"])
+    }
+}
+
+// Puts the text on the clipboard and takes it off again once the timeout is up, unless something
+// else has been copied over it by then. A clipboard that can't be read is cleared regardless.
+pub fn copy_for_a_while(
+    clipboard: Arc<dyn Clipboard>,
+    text: &str,
+    timeout: Duration,
+) -> Result<(), String> {
+    clipboard.set(text)?;
+    let text = text.to_string();
+    let handle = thread::spawn(move || {
+        thread::sleep(timeout);
+        match clipboard.get() {
+            Ok(ref current) if current.trim_end() != text => (),
+            _ => {
+                let _ = clipboard.set("");
+            }
+        }
+    });
+    PENDING_CLEARS
+        .lock()
+        .expect("Pending clipboard clears poisoned")
+        .push(handle);
+    Ok(())
+}
+
+// For a process on its way out: whatever it copied has to come off the clipboard first
+pub fn wait_for_clipboard_clears() {
+    let handles = PENDING_CLEARS
+        .lock()
+        .expect("Pending clipboard clears poisoned")
+        .drain(..)
+        .collect::<Vec<_>>();
+    handles.into_iter().for_each(|handle| {
+        let _ = handle.join();
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::clipboard_mock::ClipboardMock;
+
+    #[test]
+    fn what_was_copied_is_cleared_after_the_timeout_unless_it_was_copied_over() {
+        let cleared = Arc::new(ClipboardMock::default());
+        let copied_over = Arc::new(ClipboardMock::default());
+        let unreadable = Arc::new(ClipboardMock {
+            get_error_opt: Some("No xclip".to_string()),
+            ..ClipboardMock::default()
+        });
+        let timeout = Duration::from_millis(200);
+
+        copy_for_a_while(cleared.clone(), "0xcafebabe", timeout).unwrap();
+        copy_for_a_while(copied_over.clone(), "0xcafebabe", timeout).unwrap();
+        copied_over.set("something else").unwrap();
+        copy_for_a_while(unreadable.clone(), "0xcafebabe", timeout).unwrap();
+        wait_for_clipboard_clears();
+
+        assert_eq!(
+            *cleared.contents.lock().unwrap(),
+            vec!["0xcafebabe".to_string(), "".to_string()]
+        );
+        assert_eq!(
+            *copied_over.contents.lock().unwrap(),
+            vec!["0xcafebabe".to_string(), "something else".to_string()]
+        );
+        assert_eq!(
+            *unreadable.contents.lock().unwrap(),
+            vec!["0xcafebabe".to_string(), "".to_string()]
+        );
+    }
+
+    #[test]
+    fn nothing_is_cleared_when_nothing_could_be_copied() {
+        let subject = Arc::new(ClipboardMock {
+            set_error_opt: Some("Couldn't run xclip: not found".to_string()),
+            ..ClipboardMock::default()
+        });
+
+        let result = copy_for_a_while(subject.clone(), "0xcafebabe", Duration::from_millis(10));
+
+        assert_eq!(result, Err("Couldn't run xclip: not found".to_string()));
+        assert!(subject.contents.lock().unwrap().is_empty());
+    }
+}
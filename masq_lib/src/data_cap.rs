@@ -0,0 +1,102 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+
+use std::str::FromStr;
+
+// Bytes served from from_hour up to (but not including) to_hour, local time, count this many
+// percent against the cap; a period that wraps past midnight has from_hour > to_hour
+#[derive(Clone, Debug, PartialEq)]
+pub struct DataCapRate {
+    pub from_hour: u32,
+    pub to_hour: u32,
+    pub percent: u64,
+}
+
+impl FromStr for DataCapRate {
+    type Err = String;
+
+    // <FROM>-<TO>:<PERCENT>, like 0-6:0 for free data overnight
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let complaint = || {
+            format!(
+                "'{}' should look like <FROM-HOUR>-<TO-HOUR>:<PERCENT>, with hours from 0 to 24",
+                s
+            )
+        };
+        let mut halves = s.splitn(2, ':');
+        let hours = halves.next().ok_or_else(complaint)?;
+        let percent = halves
+            .next()
+            .and_then(|percent| percent.trim().parse::<u64>().ok())
+            .ok_or_else(complaint)?;
+        let mut bounds = hours.splitn(2, '-').map(|hour| hour.trim().parse::<u32>());
+        let (from_hour, to_hour) = match (bounds.next(), bounds.next()) {
+            (Some(Ok(from_hour)), Some(Ok(to_hour)))
+                if from_hour <= 24 && to_hour <= 24 && from_hour != to_hour =>
+            {
+                (from_hour % 24, to_hour % 24)
+            }
+            _ => return Err(complaint()),
+        };
+        Ok(DataCapRate {
+            from_hour,
+            to_hour,
+            percent,
+        })
+    }
+}
+
+impl DataCapRate {
+    pub fn covers(&self, hour: u32) -> bool {
+        if self.from_hour < self.to_hour {
+            hour >= self.from_hour && hour < self.to_hour
+        } else {
+            hour >= self.from_hour || hour < self.to_hour
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rates_are_parsed_from_hours_and_percent() {
+        assert_eq!(
+            DataCapRate::from_str("0-6:0"),
+            Ok(DataCapRate {
+                from_hour: 0,
+                to_hour: 6,
+                percent: 0
+            })
+        );
+        assert_eq!(
+            DataCapRate::from_str("22-24:50"),
+            Ok(DataCapRate {
+                from_hour: 22,
+                to_hour: 0,
+                percent: 50
+            })
+        );
+        assert_eq!(
+            DataCapRate::from_str("6-6:50"),
+            Err(
+                "'6-6:50' should look like <FROM-HOUR>-<TO-HOUR>:<PERCENT>, with hours from 0 to 24"
+                    .to_string()
+            )
+        );
+        assert_eq!(DataCapRate::from_str("0-25:50").is_err(), true);
+        assert_eq!(DataCapRate::from_str("0-6").is_err(), true);
+        assert_eq!(DataCapRate::from_str("booga").is_err(), true);
+    }
+
+    #[test]
+    fn a_rate_can_wrap_past_midnight() {
+        let subject = DataCapRate::from_str("22-6:0").unwrap();
+
+        assert_eq!(subject.covers(21), false);
+        assert_eq!(subject.covers(22), true);
+        assert_eq!(subject.covers(0), true);
+        assert_eq!(subject.covers(5), true);
+        assert_eq!(subject.covers(6), false);
+    }
+}
@@ -0,0 +1,341 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+
+use serde_derive::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+// Standard named port sets, so that exit operators can deny whole kinds of traffic at once
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ContentCategory {
+    Mail,
+    FileSharing,
+    Irc,
+    Tor,
+}
+
+const MAIL_PORTS: &[(u16, u16)] = &[(25, 25), (465, 465), (587, 587)];
+const FILE_SHARING_PORTS: &[(u16, u16)] = &[(4662, 4662), (4672, 4672), (6881, 6889), (6969, 6969)];
+const IRC_PORTS: &[(u16, u16)] = &[(6660, 6669), (6697, 6697)];
+const TOR_PORTS: &[(u16, u16)] = &[(9001, 9001), (9030, 9030), (9050, 9051), (9150, 9150)];
+
+impl FromStr for ContentCategory {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "mail" => Ok(ContentCategory::Mail),
+            "file-sharing" => Ok(ContentCategory::FileSharing),
+            "irc" => Ok(ContentCategory::Irc),
+            "tor" => Ok(ContentCategory::Tor),
+            _ => Err(format!(
+                "Content category '{}' is unknown: use mail, file-sharing, irc, or tor",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ContentCategory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            ContentCategory::Mail => "mail",
+            ContentCategory::FileSharing => "file-sharing",
+            ContentCategory::Irc => "irc",
+            ContentCategory::Tor => "tor",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl ContentCategory {
+    pub fn port_ranges(&self) -> &'static [(u16, u16)] {
+        match self {
+            ContentCategory::Mail => MAIL_PORTS,
+            ContentCategory::FileSharing => FILE_SHARING_PORTS,
+            ContentCategory::Irc => IRC_PORTS,
+            ContentCategory::Tor => TOR_PORTS,
+        }
+    }
+
+    pub fn includes_port(&self, port: u16) -> bool {
+        self.port_ranges()
+            .iter()
+            .any(|(low, high)| port >= *low && port <= *high)
+    }
+}
+
+// What an exit Node refuses to serve. Written as a comma-separated list such as
+// port:25,port:6881-6889,host:example.com,category:mail, and advertised in the Node record
+// so that other Nodes can route around it. An empty policy permits everything.
+#[derive(Clone, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct ExitPolicy {
+    pub denied_ports: Vec<(u16, u16)>,
+    pub denied_hosts: Vec<String>,
+    pub denied_categories: Vec<ContentCategory>,
+}
+
+impl FromStr for ExitPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut policy = ExitPolicy::default();
+        for entry in s.split(',').map(|e| e.trim()).filter(|e| !e.is_empty()) {
+            let (kind, value) = match entry.find(':') {
+                Some(idx) => (&entry[..idx], entry[(idx + 1)..].trim()),
+                None => {
+                    return Err(format!(
+                        "Exit policy entry '{}' must be port:, host:, or category:",
+                        entry
+                    ))
+                }
+            };
+            match kind.trim().to_lowercase().as_str() {
+                "port" => policy
+                    .denied_ports
+                    .push(Self::parse_port_range(entry, value)?),
+                "host" => policy.denied_hosts.push(Self::parse_host(entry, value)?),
+                "category" => policy
+                    .denied_categories
+                    .push(ContentCategory::from_str(value)?),
+                _ => {
+                    return Err(format!(
+                        "Exit policy entry '{}' must be port:, host:, or category:",
+                        entry
+                    ))
+                }
+            }
+        }
+        Ok(policy)
+    }
+}
+
+impl fmt::Display for ExitPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let ports = self.denied_ports.iter().map(|(low, high)| {
+            if low == high {
+                format!("port:{}", low)
+            } else {
+                format!("port:{}-{}", low, high)
+            }
+        });
+        let hosts = self
+            .denied_hosts
+            .iter()
+            .map(|host| format!("host:{}", host));
+        let categories = self
+            .denied_categories
+            .iter()
+            .map(|category| format!("category:{}", category));
+        let entries = ports
+            .chain(hosts)
+            .chain(categories)
+            .collect::<Vec<String>>();
+        write!(f, "{}", entries.join(","))
+    }
+}
+
+impl ExitPolicy {
+    pub fn is_empty(&self) -> bool {
+        self.denied_ports.is_empty()
+            && self.denied_hosts.is_empty()
+            && self.denied_categories.is_empty()
+    }
+
+    pub fn permits(&self, hostname_opt: Option<&str>, port: u16) -> bool {
+        self.denial_reason_opt(hostname_opt, port).is_none()
+    }
+
+    pub fn denial_reason_opt(&self, hostname_opt: Option<&str>, port: u16) -> Option<String> {
+        if let Some((low, high)) = self
+            .denied_ports
+            .iter()
+            .find(|(low, high)| port >= *low && port <= *high)
+        {
+            return Some(if low == high {
+                format!("port {} is denied", port)
+            } else {
+                format!("port {} is in denied range {}-{}", port, low, high)
+            });
+        }
+        if let Some(category) = self
+            .denied_categories
+            .iter()
+            .find(|category| category.includes_port(port))
+        {
+            return Some(format!("port {} is {} traffic", port, category));
+        }
+        if let Some(hostname) = hostname_opt {
+            let hostname = hostname.trim_end_matches('.').to_lowercase();
+            if let Some(host) = self
+                .denied_hosts
+                .iter()
+                .find(|host| hostname == **host || hostname.ends_with(&format!(".{}", host)))
+            {
+                return Some(format!("host {} is denied by rule {}", hostname, host));
+            }
+        }
+        None
+    }
+
+    fn parse_port_range(entry: &str, value: &str) -> Result<(u16, u16), String> {
+        let parse_port = |port_str: &str| match u16::from_str(port_str.trim()) {
+            Ok(port) if port > 0 => Ok(port),
+            _ => Err(format!("Exit policy entry '{}' has an invalid port", entry)),
+        };
+        let (low, high) = match value.find('-') {
+            Some(idx) => (parse_port(&value[..idx])?, parse_port(&value[(idx + 1)..])?),
+            None => {
+                let port = parse_port(value)?;
+                (port, port)
+            }
+        };
+        if low > high {
+            Err(format!(
+                "Exit policy entry '{}' has a port range that runs backward",
+                entry
+            ))
+        } else {
+            Ok((low, high))
+        }
+    }
+
+    fn parse_host(entry: &str, value: &str) -> Result<String, String> {
+        let host = value
+            .trim_start_matches("*.")
+            .trim_end_matches('.')
+            .to_lowercase();
+        let is_bad_label = |label: &str| {
+            label.is_empty()
+                || !label
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        };
+        if host.split('.').any(is_bad_label) {
+            Err(format!(
+                "Exit policy entry '{}' is not a valid host name",
+                entry
+            ))
+        } else {
+            Ok(host)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exit_policy_is_parsed_and_normalized() {
+        let result =
+            ExitPolicy::from_str(" port:25, port:6881-6889,host:*.Example.COM., category:Tor ,");
+
+        assert_eq!(
+            result,
+            Ok(ExitPolicy {
+                denied_ports: vec![(25, 25), (6881, 6889)],
+                denied_hosts: vec!["example.com".to_string()],
+                denied_categories: vec![ContentCategory::Tor],
+            })
+        );
+    }
+
+    #[test]
+    fn empty_string_is_an_empty_exit_policy() {
+        let result = ExitPolicy::from_str("").unwrap();
+
+        assert!(result.is_empty());
+        assert!(result.permits(Some("example.com"), 25));
+        assert_eq!(result.to_string(), "".to_string());
+    }
+
+    #[test]
+    fn exit_policy_round_trips_through_display() {
+        let policy_str = "port:25,port:6881-6889,host:example.com,category:file-sharing";
+
+        let result = ExitPolicy::from_str(policy_str).unwrap().to_string();
+
+        assert_eq!(result, policy_str.to_string());
+    }
+
+    #[test]
+    fn bad_exit_policy_entries_are_rejected() {
+        assert_eq!(
+            ExitPolicy::from_str("25"),
+            Err("Exit policy entry '25' must be port:, host:, or category:".to_string())
+        );
+        assert_eq!(
+            ExitPolicy::from_str("protocol:smtp"),
+            Err("Exit policy entry 'protocol:smtp' must be port:, host:, or category:".to_string())
+        );
+        assert_eq!(
+            ExitPolicy::from_str("port:0"),
+            Err("Exit policy entry 'port:0' has an invalid port".to_string())
+        );
+        assert_eq!(
+            ExitPolicy::from_str("port:70000"),
+            Err("Exit policy entry 'port:70000' has an invalid port".to_string())
+        );
+        assert_eq!(
+            ExitPolicy::from_str("port:443-80"),
+            Err("Exit policy entry 'port:443-80' has a port range that runs backward".to_string())
+        );
+        assert_eq!(
+            ExitPolicy::from_str("host:exa mple.com"),
+            Err("Exit policy entry 'host:exa mple.com' is not a valid host name".to_string())
+        );
+        assert_eq!(
+            ExitPolicy::from_str("category:gambling"),
+            Err(
+                "Content category 'gambling' is unknown: use mail, file-sharing, irc, or tor"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn denied_ports_and_ranges_are_refused() {
+        let subject = ExitPolicy::from_str("port:25,port:6881-6889").unwrap();
+
+        assert_eq!(
+            subject.denial_reason_opt(Some("mail.example.com"), 25),
+            Some("port 25 is denied".to_string())
+        );
+        assert_eq!(
+            subject.denial_reason_opt(None, 6885),
+            Some("port 6885 is in denied range 6881-6889".to_string())
+        );
+        assert!(subject.permits(Some("example.com"), 443));
+        assert!(subject.permits(None, 6890));
+    }
+
+    #[test]
+    fn denied_hosts_cover_their_subdomains() {
+        let subject = ExitPolicy::from_str("host:example.com").unwrap();
+
+        assert_eq!(
+            subject.denial_reason_opt(Some("WWW.Example.com."), 443),
+            Some("host www.example.com is denied by rule example.com".to_string())
+        );
+        assert!(!subject.permits(Some("example.com"), 80));
+        assert!(subject.permits(Some("notexample.com"), 443));
+        assert!(subject.permits(None, 443));
+    }
+
+    #[test]
+    fn denied_categories_cover_their_standard_ports() {
+        let subject = ExitPolicy::from_str("category:mail,category:irc").unwrap();
+
+        assert_eq!(
+            subject.denial_reason_opt(None, 587),
+            Some("port 587 is mail traffic".to_string())
+        );
+        assert_eq!(
+            subject.denial_reason_opt(Some("irc.example.com"), 6667),
+            Some("port 6667 is irc traffic".to_string())
+        );
+        assert!(subject.permits(None, 9050));
+        assert!(ContentCategory::Tor.includes_port(9050));
+        assert!(ContentCategory::FileSharing.includes_port(6969));
+    }
+}
@@ -0,0 +1,112 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+// A CIDR network, such as 192.168.0.0/24. A bare address is a network of one.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct IpNetwork {
+    pub address: IpAddr,
+    pub prefix: u8,
+}
+
+impl FromStr for IpNetwork {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let network = s.trim();
+        let (address_str, prefix_str_opt) = match network.find('/') {
+            Some(idx) => (&network[..idx], Some(&network[(idx + 1)..])),
+            None => (network, None),
+        };
+        let address = IpAddr::from_str(address_str)
+            .map_err(|_| format!("'{}' is not a valid CIDR network", network))?;
+        let max_prefix = if address.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix_str_opt {
+            None => max_prefix,
+            Some(prefix_str) => match u8::from_str(prefix_str) {
+                Ok(prefix) if prefix <= max_prefix => prefix,
+                _ => return Err(format!("'{}' has an invalid network prefix", network)),
+            },
+        };
+        Ok(IpNetwork { address, prefix })
+    }
+}
+
+impl fmt::Display for IpNetwork {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.address, self.prefix)
+    }
+}
+
+impl IpNetwork {
+    pub fn contains(&self, address: IpAddr) -> bool {
+        let (address_bits, network_bits, width) = match (address, self.address) {
+            (IpAddr::V4(a), IpAddr::V4(n)) => (u32::from(a) as u128, u32::from(n) as u128, 32u32),
+            (IpAddr::V6(a), IpAddr::V6(n)) => (u128::from(a), u128::from(n), 128u32),
+            _ => return false,
+        };
+        if self.prefix == 0 {
+            return true;
+        }
+        let shift = width - self.prefix as u32;
+        (address_bits >> shift) == (network_bits >> shift)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn networks_and_bare_addresses_are_parsed() {
+        assert_eq!(
+            IpNetwork::from_str("192.168.0.0/24"),
+            Ok(IpNetwork {
+                address: IpAddr::from_str("192.168.0.0").unwrap(),
+                prefix: 24
+            })
+        );
+        assert_eq!(
+            IpNetwork::from_str(" 2001:db8::1 "),
+            Ok(IpNetwork {
+                address: IpAddr::from_str("2001:db8::1").unwrap(),
+                prefix: 128
+            })
+        );
+        assert_eq!(
+            IpNetwork::from_str("10.0.0.0/33"),
+            Err("'10.0.0.0/33' has an invalid network prefix".to_string())
+        );
+        assert_eq!(
+            IpNetwork::from_str("booga/8"),
+            Err("'booga/8' is not a valid CIDR network".to_string())
+        );
+    }
+
+    #[test]
+    fn network_contains_only_addresses_in_the_network() {
+        let subject = IpNetwork::from_str("10.1.0.0/16").unwrap();
+
+        assert_eq!(
+            subject.contains(IpAddr::from_str("10.1.200.3").unwrap()),
+            true
+        );
+        assert_eq!(
+            subject.contains(IpAddr::from_str("10.2.0.1").unwrap()),
+            false
+        );
+        assert_eq!(
+            subject.contains(IpAddr::from_str("::ffff:10.1.0.1").unwrap()),
+            false
+        );
+        assert_eq!(
+            IpNetwork::from_str("0.0.0.0/0")
+                .unwrap()
+                .contains(IpAddr::from_str("1.2.3.4").unwrap()),
+            true
+        );
+        assert_eq!(subject.to_string(), "10.1.0.0/16");
+    }
+}
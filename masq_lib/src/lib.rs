@@ -6,9 +6,15 @@ pub mod multi_config;
 #[macro_use]
 pub mod messages;
 
+#[cfg(any(test, feature = "clipboard"))]
+pub mod clipboard;
 pub mod command;
 pub mod constants;
 pub mod crash_point;
+pub mod data_cap;
+pub mod exit_policy;
+pub mod ip_network;
+pub mod secure_dns;
 pub mod shared_schema;
 pub mod test_utils;
 pub mod ui_gateway;
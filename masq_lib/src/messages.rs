@@ -1,7 +1,7 @@
 // Copyright (c) 2019-2020, MASQ (https://masq.ai). All rights reserved.
 
 use crate::messages::UiMessageError::{DeserializationError, PayloadError, UnexpectedMessage};
-use crate::shared_schema::ConfiguratorError;
+use crate::shared_schema::{ConfiguratorError, ParamError, ParamErrorCode};
 use crate::ui_gateway::MessagePath::{Conversation, FireAndForget};
 use crate::ui_gateway::{MessageBody, MessagePath};
 use itertools::Itertools;
@@ -21,6 +21,23 @@ pub const NODE_ALREADY_RUNNING_ERROR: u64 = 0x8000_0000_0000_0003;
 pub const UNMARSHAL_ERROR: u64 = 0x8000_0000_0000_0004;
 pub const SETUP_ERROR: u64 = 0x8000_0000_0000_0005;
 pub const TIMEOUT_ERROR: u64 = 0x8000_0000_0000_0006;
+pub const SPLIT_TUNNEL_RULES_ERROR: u64 = 0x8000_0000_0000_0007;
+pub const ENCRYPTED_SNI_ERROR: u64 = 0x8000_0000_0000_0008;
+pub const APPLICATION_RULES_ERROR: u64 = 0x8000_0000_0000_0009;
+pub const AUTHENTICATION_ERROR: u64 = 0x8000_0000_0000_000A;
+pub const PERMISSION_ERROR: u64 = 0x8000_0000_0000_000B;
+pub const SUBSCRIPTION_ERROR: u64 = 0x8000_0000_0000_000C;
+pub const LOG_LEVEL_ERROR: u64 = 0x8000_0000_0000_000D;
+pub const LOG_ROTATION_ERROR: u64 = 0x8000_0000_0000_000E;
+pub const DIAGNOSTICS_ERROR: u64 = 0x8000_0000_0000_000F;
+pub const DNS_ERROR: u64 = 0x8000_0000_0000_0010;
+pub const BLOCKCHAIN_QUERY_ERROR: u64 = 0x8000_0000_0000_0011;
+pub const ROUTE_QUOTE_ERROR: u64 = 0x8000_0000_0000_0012;
+pub const CONNECTION_AUDIT_ERROR: u64 = 0x8000_0000_0000_0013;
+pub const GEO_DATABASE_ERROR: u64 = 0x8000_0000_0000_0014;
+pub const DUPLICATE_REQUEST_ERROR: u64 = 0x8000_0000_0000_0015;
+pub const SPEED_TEST_ERROR: u64 = 0x8000_0000_0000_0016;
+pub const TEST_FUNDS_ERROR: u64 = 0x8000_0000_0000_0017;
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum UiMessageError {
@@ -217,6 +234,8 @@ impl UiSetupRequestValue {
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct UiSetupRequest {
     pub values: Vec<UiSetupRequestValue>,
+    #[serde(rename = "instance")]
+    pub instance_opt: Option<String>,
 }
 conversation_message!(UiSetupRequest, "setup");
 
@@ -230,6 +249,7 @@ impl UiSetupRequest {
                     value: value.map(|v| v.to_string()),
                 })
                 .collect(),
+            instance_opt: None,
         }
     }
 }
@@ -276,11 +296,40 @@ impl UiSetupResponseValue {
     }
 }
 
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiSetupResponseError {
+    pub parameter: String,
+    pub code: ParamErrorCode,
+    pub message: String,
+}
+
+impl UiSetupResponseError {
+    pub fn new(parameter: &str, code: ParamErrorCode, message: &str) -> UiSetupResponseError {
+        UiSetupResponseError {
+            parameter: parameter.to_string(),
+            code,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl From<ParamError> for UiSetupResponseError {
+    fn from(input: ParamError) -> Self {
+        UiSetupResponseError {
+            parameter: input.parameter,
+            code: input.code,
+            message: input.reason,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct UiSetupResponse {
     pub running: bool,
     pub values: Vec<UiSetupResponseValue>,
-    pub errors: Vec<(String, String)>,
+    pub errors: Vec<UiSetupResponseError>,
+    #[serde(rename = "instance")]
+    pub instance_opt: Option<String>,
 }
 conversation_message!(UiSetupResponse, "setup");
 impl UiSetupResponse {
@@ -288,6 +337,7 @@ impl UiSetupResponse {
         running: bool,
         values: HashMap<String, UiSetupResponseValue>,
         errors: ConfiguratorError,
+        instance_opt: Option<String>,
     ) -> UiSetupResponse {
         UiSetupResponse {
             running,
@@ -299,8 +349,9 @@ impl UiSetupResponse {
             errors: errors
                 .param_errors
                 .into_iter()
-                .map(|pe| (pe.parameter, pe.reason))
+                .map(UiSetupResponseError::from)
                 .collect(),
+            instance_opt,
         }
     }
 }
@@ -309,7 +360,9 @@ impl UiSetupResponse {
 pub struct UiSetupBroadcast {
     pub running: bool,
     pub values: Vec<UiSetupResponseValue>,
-    pub errors: Vec<(String, String)>,
+    pub errors: Vec<UiSetupResponseError>,
+    #[serde(rename = "instance")]
+    pub instance_opt: Option<String>,
 }
 fire_and_forget_message!(UiSetupBroadcast, "setup");
 impl UiSetupBroadcast {
@@ -317,6 +370,7 @@ impl UiSetupBroadcast {
         running: bool,
         values: HashMap<String, UiSetupResponseValue>,
         errors: ConfiguratorError,
+        instance_opt: Option<String>,
     ) -> UiSetupBroadcast {
         UiSetupBroadcast {
             running,
@@ -328,8 +382,9 @@ impl UiSetupBroadcast {
             errors: errors
                 .param_errors
                 .into_iter()
-                .map(|pe| (pe.parameter, pe.reason))
+                .map(UiSetupResponseError::from)
                 .collect(),
+            instance_opt,
         }
     }
 }
@@ -338,7 +393,7 @@ impl UiSetupBroadcast {
 pub struct UiSetupInner {
     pub running: bool,
     pub values: Vec<UiSetupResponseValue>,
-    pub errors: Vec<(String, String)>,
+    pub errors: Vec<UiSetupResponseError>,
 }
 
 impl From<UiSetupResponse> for UiSetupInner {
@@ -362,7 +417,10 @@ impl From<UiSetupBroadcast> for UiSetupInner {
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
-pub struct UiStartOrder {}
+pub struct UiStartOrder {
+    #[serde(rename = "instance")]
+    pub instance_opt: Option<String>,
+}
 conversation_message!(UiStartOrder, "start");
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
@@ -388,8 +446,28 @@ pub struct UiNodeCrashedBroadcast {
     pub process_id: u32,
     #[serde(rename = "crashReason")]
     pub crash_reason: CrashReason,
+    #[serde(rename = "exitCode")]
+    pub exit_code_opt: Option<i32>,
+    #[serde(rename = "lastLogLines")]
+    pub last_log_lines: Vec<String>,
+    #[serde(rename = "restartDelaySeconds")]
+    pub restart_delay_secs_opt: Option<u64>,
+    #[serde(rename = "instance")]
+    pub instance_opt: Option<String>,
 }
-fire_and_forget_message!(UiNodeCrashedBroadcast, "crashed");
+fire_and_forget_message!(UiNodeCrashedBroadcast, "nodeCrashed");
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct UiNodeRestartedBroadcast {
+    #[serde(rename = "newProcessId")]
+    pub new_process_id: u32,
+    #[serde(rename = "redirectUiPort")]
+    pub redirect_ui_port: u16,
+    pub attempt: u32,
+    #[serde(rename = "instance")]
+    pub instance_opt: Option<String>,
+}
+fire_and_forget_message!(UiNodeRestartedBroadcast, "nodeRestarted");
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
 pub struct UiRedirect {
@@ -401,6 +479,85 @@ pub struct UiRedirect {
 }
 fire_and_forget_message!(UiRedirect, "redirect");
 
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiInstancesRequest {}
+conversation_message!(UiInstancesRequest, "instances");
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiInstance {
+    pub name: String,
+    pub running: bool,
+    #[serde(rename = "processId")]
+    pub process_id_opt: Option<u32>,
+    #[serde(rename = "uiPort")]
+    pub ui_port_opt: Option<u16>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiInstancesResponse {
+    pub instances: Vec<UiInstance>,
+}
+conversation_message!(UiInstancesResponse, "instances");
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiConfigurationSchemaRequest {}
+conversation_message!(UiConfigurationSchemaRequest, "configurationSchema");
+
+// type is "choice" (one of possibleValues), "integer" (between minimum and maximum inclusive), or
+// "string". restartRequired is false only for parameters a running Node can also be told to change.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiConfigurationParameter {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub parameter_type: String,
+    pub help: String,
+    #[serde(rename = "possibleValues")]
+    pub possible_values: Vec<String>,
+    #[serde(rename = "minimum")]
+    pub minimum_opt: Option<u64>,
+    #[serde(rename = "maximum")]
+    pub maximum_opt: Option<u64>,
+    #[serde(rename = "default")]
+    pub default_opt: Option<String>,
+    #[serde(rename = "restartRequired")]
+    pub restart_required: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiConfigurationSchemaResponse {
+    pub parameters: Vec<UiConfigurationParameter>,
+}
+conversation_message!(UiConfigurationSchemaResponse, "configurationSchema");
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiDnsStatusRequest {}
+conversation_message!(UiDnsStatusRequest, "dnsStatus");
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiDnsStatusResponse {
+    pub status: String,
+    pub nameservers: Vec<String>,
+    #[serde(rename = "managedBy")]
+    pub managed_by_opt: Option<String>,
+}
+conversation_message!(UiDnsStatusResponse, "dnsStatus");
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiDnsSubvertRequest {}
+conversation_message!(UiDnsSubvertRequest, "dnsSubvert");
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiDnsSubvertResponse {}
+conversation_message!(UiDnsSubvertResponse, "dnsSubvert");
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiDnsRevertRequest {}
+conversation_message!(UiDnsRevertRequest, "dnsRevert");
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiDnsRevertResponse {}
+conversation_message!(UiDnsRevertResponse, "dnsRevert");
+
 ///////////////////////////////////////////////////////////////////
 // These messages are sent to or by both the Daemon and the Node
 ///////////////////////////////////////////////////////////////////
@@ -428,6 +585,223 @@ pub struct UiDescriptorResponse {
 }
 conversation_message!(UiDescriptorResponse, "descriptor");
 
+// What the Node is running with, once it has started: booleans only where wallets are concerned
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct UiEffectiveConfig {
+    pub chain: String,
+    #[serde(rename = "chainId")]
+    pub chain_id: u8,
+    #[serde(rename = "clandestinePort")]
+    pub clandestine_port_opt: Option<u16>,
+    #[serde(rename = "uiPort")]
+    pub ui_port: u16,
+    #[serde(rename = "dnsPort")]
+    pub dns_port: u16,
+    #[serde(rename = "socksPort")]
+    pub socks_port_opt: Option<u16>,
+    #[serde(rename = "metricsPort")]
+    pub metrics_port_opt: Option<u16>,
+    #[serde(rename = "neighborhoodMode")]
+    pub neighborhood_mode: String,
+    #[serde(rename = "hasConsumingWallet")]
+    pub has_consuming_wallet: bool,
+    #[serde(rename = "hasEarningWallet")]
+    pub has_earning_wallet: bool,
+    #[serde(rename = "nodeVersion")]
+    pub node_version: String,
+    #[serde(rename = "schemaVersion")]
+    pub schema_version: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct UiEffectiveConfigBroadcast {
+    pub config: UiEffectiveConfig,
+}
+fire_and_forget_message!(UiEffectiveConfigBroadcast, "effectiveConfig");
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct UiEffectiveConfigRequest {}
+conversation_message!(UiEffectiveConfigRequest, "effectiveConfig");
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct UiEffectiveConfigResponse {
+    pub config: UiEffectiveConfig,
+}
+conversation_message!(UiEffectiveConfigResponse, "effectiveConfig");
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiNeighborhoodGraphRequest {}
+conversation_message!(UiNeighborhoodGraphRequest, "neighborhoodGraph");
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiRatePack {
+    #[serde(rename = "routingByteRate")]
+    pub routing_byte_rate: u64,
+    #[serde(rename = "routingServiceRate")]
+    pub routing_service_rate: u64,
+    #[serde(rename = "exitByteRate")]
+    pub exit_byte_rate: u64,
+    #[serde(rename = "exitServiceRate")]
+    pub exit_service_rate: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiNeighborhoodGraphNode {
+    #[serde(rename = "publicKey")]
+    pub public_key: String,
+    #[serde(rename = "isLocal")]
+    pub is_local: bool,
+    pub version: u32,
+    #[serde(rename = "acceptsConnections")]
+    pub accepts_connections: bool,
+    #[serde(rename = "routesData")]
+    pub routes_data: bool,
+    #[serde(rename = "ratePack")]
+    pub rate_pack: UiRatePack,
+    #[serde(rename = "countryCode")]
+    pub country_code_opt: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiNeighborhoodGraphEdge {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiNeighborhoodGraphResponse {
+    pub nodes: Vec<UiNeighborhoodGraphNode>,
+    pub edges: Vec<UiNeighborhoodGraphEdge>,
+    #[serde(rename = "dotGraph")]
+    pub dot_graph: String,
+}
+conversation_message!(UiNeighborhoodGraphResponse, "neighborhoodGraph");
+
+// Without hopCounts, the Node quotes every hop count from 1 to the one it uses for its own traffic
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiRouteQuoteRequest {
+    #[serde(rename = "hopCounts")]
+    pub hop_counts_opt: Option<Vec<usize>>,
+}
+conversation_message!(UiRouteQuoteRequest, "routeQuote");
+
+// What the route the Node would choose right now would cost, at the rates its Nodes gossip
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiRouteQuote {
+    #[serde(rename = "hopCount")]
+    pub hop_count: usize,
+    #[serde(rename = "exitKey")]
+    pub exit_key: String,
+    #[serde(rename = "requestCostPerMb")]
+    pub request_cost_per_mb: u64,
+    #[serde(rename = "responseCostPerMb")]
+    pub response_cost_per_mb: u64,
+    #[serde(rename = "serviceCost")]
+    pub service_cost: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiRouteQuoteResponse {
+    pub quotes: Vec<UiRouteQuote>,
+    #[serde(rename = "unavailableHopCounts")]
+    pub unavailable_hop_counts: Vec<usize>,
+}
+conversation_message!(UiRouteQuoteResponse, "routeQuote");
+
+// Without hopCounts, the Node tests every hop count from 1 to the one it uses for its own traffic
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiSpeedTestRequest {
+    #[serde(rename = "hopCounts")]
+    pub hop_counts_opt: Option<Vec<usize>>,
+}
+conversation_message!(UiSpeedTestRequest, "speedTest");
+
+// How a probe fared on its way to an exit and back: the round trip of an empty one, and the rate
+// at which a padded one's padding crossed the route, there and back
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiSpeedTestResult {
+    #[serde(rename = "hopCount")]
+    pub hop_count: usize,
+    #[serde(rename = "exitKey")]
+    pub exit_key: String,
+    #[serde(rename = "latencyMs")]
+    pub latency_ms: u32,
+    #[serde(rename = "throughputKbps")]
+    pub throughput_kbps: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiSpeedTestResponse {
+    pub results: Vec<UiSpeedTestResult>,
+    #[serde(rename = "unavailableHopCounts")]
+    pub unavailable_hop_counts: Vec<usize>,
+}
+conversation_message!(UiSpeedTestResponse, "speedTest");
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiConnectionAuditRequest {
+    #[serde(rename = "fromTimestamp")]
+    pub from_timestamp_opt: Option<u64>,
+    #[serde(rename = "toTimestamp")]
+    pub to_timestamp_opt: Option<u64>,
+    #[serde(rename = "limit")]
+    pub limit_opt: Option<u64>,
+}
+conversation_message!(UiConnectionAuditRequest, "connectionAudit");
+
+// A clandestine connection that has closed, as the connection audit log remembers it
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiAuditedConnection {
+    #[serde(rename = "publicKey")]
+    pub public_key_opt: Option<String>,
+    #[serde(rename = "peerAddr")]
+    pub peer_addr: String,
+    pub direction: String,
+    #[serde(rename = "openedAt")]
+    pub opened_at: u64,
+    #[serde(rename = "durationMs")]
+    pub duration_ms: u64,
+    #[serde(rename = "bytesIn")]
+    pub bytes_in: u64,
+    #[serde(rename = "bytesOut")]
+    pub bytes_out: u64,
+    #[serde(rename = "disconnectReason")]
+    pub disconnect_reason: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiConnectionAuditResponse {
+    pub connections: Vec<UiAuditedConnection>,
+}
+conversation_message!(UiConnectionAuditResponse, "connectionAudit");
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiGeoDatabaseRequest {}
+conversation_message!(UiGeoDatabaseRequest, "geoDatabase");
+
+// Times are in seconds since the epoch
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiGeoDatabaseResponse {
+    #[serde(rename = "mirrorUrl")]
+    pub mirror_url: String,
+    #[serde(rename = "ageSeconds")]
+    pub age_seconds_opt: Option<u64>,
+    #[serde(rename = "lastChecked")]
+    pub last_checked_opt: Option<u64>,
+    #[serde(rename = "lastError")]
+    pub last_error_opt: Option<String>,
+    pub refreshing: bool,
+}
+conversation_message!(UiGeoDatabaseResponse, "geoDatabase");
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiGeoDatabaseRefreshRequest {}
+conversation_message!(UiGeoDatabaseRefreshRequest, "geoDatabaseRefresh");
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiGeoDatabaseRefreshResponse {}
+conversation_message!(UiGeoDatabaseRefreshResponse, "geoDatabaseRefresh");
+
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
 pub struct UiPayableAccount {
     pub wallet: String,
@@ -468,6 +842,217 @@ pub struct UiFinancialsResponse {
 }
 conversation_message!(UiFinancialsResponse, "financials");
 
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiWalletBalancesRequest {}
+conversation_message!(UiWalletBalancesRequest, "walletBalances");
+
+// Balances are in wei, as decimal strings, because they can be too big for a u64
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiWalletBalances {
+    pub address: String,
+    #[serde(rename = "gasBalance")]
+    pub gas_balance: String,
+    #[serde(rename = "masqBalance")]
+    pub masq_balance: String,
+}
+
+// age is how many seconds ago the balances were read from the blockchain. canConsume is false for
+// an earning-only Node, which holds no consuming wallet and so can't pay for traffic of its own.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiWalletBalancesResponse {
+    #[serde(rename = "consumingWallet")]
+    pub consuming_wallet_opt: Option<UiWalletBalances>,
+    #[serde(rename = "earningWallet")]
+    pub earning_wallet: UiWalletBalances,
+    #[serde(rename = "canConsume")]
+    pub can_consume: bool,
+    pub age: u64,
+}
+conversation_message!(UiWalletBalancesResponse, "walletBalances");
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiRequestTestFundsRequest {}
+conversation_message!(UiRequestTestFundsRequest, "requestTestFunds");
+
+// address is the consuming wallet that was funded; transaction is the hash of the transaction the
+// faucet sent the funds in, as the faucet wrote it
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiRequestTestFundsResponse {
+    pub address: String,
+    pub transaction: String,
+}
+conversation_message!(UiRequestTestFundsResponse, "requestTestFunds");
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiUsageHistoryRequest {
+    pub days: u64,
+}
+conversation_message!(UiUsageHistoryRequest, "usageHistory");
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiUsageDay {
+    pub date: String,
+    #[serde(rename = "uptimeSeconds")]
+    pub uptime_seconds: u64,
+    #[serde(rename = "routedBytes")]
+    pub routed_bytes: u64,
+    #[serde(rename = "earnedGwei")]
+    pub earned_gwei: u64,
+    #[serde(rename = "neighborsGained")]
+    pub neighbors_gained: u64,
+    #[serde(rename = "neighborsLost")]
+    pub neighbors_lost: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiUsageHistoryResponse {
+    pub days: Vec<UiUsageDay>,
+}
+conversation_message!(UiUsageHistoryResponse, "usageHistory");
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiBalanceHistoryRequest {
+    pub days: u64,
+}
+conversation_message!(UiBalanceHistoryRequest, "balanceHistory");
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiBalanceDay {
+    pub date: String,
+    pub address: String,
+    #[serde(rename = "gasBalance")]
+    pub gas_balance: String,
+    #[serde(rename = "masqBalance")]
+    pub masq_balance: String,
+    #[serde(rename = "masqPrice")]
+    pub masq_price_opt: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiBalanceHistoryResponse {
+    pub days: Vec<UiBalanceDay>,
+}
+conversation_message!(UiBalanceHistoryResponse, "balanceHistory");
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiDataCapRequest {}
+conversation_message!(UiDataCapRequest, "dataCap");
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiDataCapResponse {
+    pub month: String,
+    #[serde(rename = "monthlyBytes")]
+    pub monthly_bytes_opt: Option<u64>,
+    #[serde(rename = "usedBytes")]
+    pub used_bytes_opt: Option<u64>,
+    #[serde(rename = "remainingBytes")]
+    pub remaining_bytes_opt: Option<u64>,
+    pub status: String,
+}
+conversation_message!(UiDataCapResponse, "dataCap");
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiSplitTunnelRulesRequest {}
+conversation_message!(UiSplitTunnelRulesRequest, "splitTunnelRules");
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiSplitTunnelRulesResponse {
+    pub rules: Vec<String>,
+}
+conversation_message!(UiSplitTunnelRulesResponse, "splitTunnelRules");
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiSetSplitTunnelRulesRequest {
+    pub rules: Vec<String>,
+}
+conversation_message!(UiSetSplitTunnelRulesRequest, "setSplitTunnelRules");
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiSetSplitTunnelRulesResponse {}
+conversation_message!(UiSetSplitTunnelRulesResponse, "setSplitTunnelRules");
+
+// A TLS connection that hides its server's name is waiting to be told where to go
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiEncryptedSniBroadcast {
+    #[serde(rename = "connectionId")]
+    pub connection_id: u64,
+    #[serde(rename = "clientAddress")]
+    pub client_address: String,
+}
+fire_and_forget_message!(UiEncryptedSniBroadcast, "encryptedSni");
+
+// A missing host name refuses the connection
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiEncryptedSniHostRequest {
+    #[serde(rename = "connectionId")]
+    pub connection_id: u64,
+    #[serde(rename = "hostName")]
+    pub host_name_opt: Option<String>,
+}
+conversation_message!(UiEncryptedSniHostRequest, "encryptedSniHost");
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiEncryptedSniHostResponse {}
+conversation_message!(UiEncryptedSniHostResponse, "encryptedSniHost");
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiApplicationRulesRequest {}
+conversation_message!(UiApplicationRulesRequest, "applicationRules");
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiApplicationRulesResponse {
+    pub rules: Vec<String>,
+}
+conversation_message!(UiApplicationRulesResponse, "applicationRules");
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiSetApplicationRulesRequest {
+    pub rules: Vec<String>,
+}
+conversation_message!(UiSetApplicationRulesRequest, "setApplicationRules");
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiSetApplicationRulesResponse {}
+conversation_message!(UiSetApplicationRulesResponse, "setApplicationRules");
+
+// From a companion program, such as a firewall, that knows which application opened a connection
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiApplicationOriginReport {
+    #[serde(rename = "clientAddress")]
+    pub client_address: String,
+    pub application: String,
+}
+fire_and_forget_message!(UiApplicationOriginReport, "applicationOrigin");
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiStreamsRequest {}
+conversation_message!(UiStreamsRequest, "streams");
+
+// Bytes out travel toward the target host, bytes in come back from it
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiStream {
+    #[serde(rename = "streamKey")]
+    pub stream_key: String,
+    pub protocol: String,
+    #[serde(rename = "targetHost")]
+    pub target_host_opt: Option<String>,
+    #[serde(rename = "bytesOut")]
+    pub bytes_out: u64,
+    #[serde(rename = "bytesIn")]
+    pub bytes_in: u64,
+    #[serde(rename = "hopCount")]
+    pub hop_count_opt: Option<usize>,
+    #[serde(rename = "ageSeconds")]
+    pub age_seconds: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiStreamsResponse {
+    pub consuming: Vec<UiStream>,
+    pub serving: Vec<UiStream>,
+}
+conversation_message!(UiStreamsResponse, "streams");
+
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct UiShutdownRequest {}
 conversation_message!(UiShutdownRequest, "shutdown");
@@ -476,6 +1061,208 @@ conversation_message!(UiShutdownRequest, "shutdown");
 pub struct UiShutdownResponse {}
 conversation_message!(UiShutdownResponse, "shutdown");
 
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiAuthenticateRequest {
+    pub password: String,
+}
+conversation_message!(UiAuthenticateRequest, "authenticate");
+
+// "readOnly" or "full"
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiAuthenticateResponse {
+    pub permission: String,
+}
+conversation_message!(UiAuthenticateResponse, "authenticate");
+
+// Opcodes are those of the broadcasts below; logLevel and logTargets only matter for logs
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiSubscribeRequest {
+    pub opcodes: Vec<String>,
+    #[serde(rename = "logLevel")]
+    pub log_level_opt: Option<String>,
+    #[serde(rename = "logTargets")]
+    pub log_targets_opt: Option<Vec<String>>,
+}
+conversation_message!(UiSubscribeRequest, "subscribe");
+
+// Everything the client is subscribed to now
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiSubscribeResponse {
+    pub opcodes: Vec<String>,
+}
+conversation_message!(UiSubscribeResponse, "subscribe");
+
+// An empty list unsubscribes from everything
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiUnsubscribeRequest {
+    pub opcodes: Vec<String>,
+}
+conversation_message!(UiUnsubscribeRequest, "unsubscribe");
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiUnsubscribeResponse {
+    pub opcodes: Vec<String>,
+}
+conversation_message!(UiUnsubscribeResponse, "unsubscribe");
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiTargetLogLevel {
+    pub target: String,
+    pub level: String,
+}
+
+// Leave a field out to keep it as it is. targetLevels replaces every per-target level; an empty list clears them.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiLogLevelRequest {
+    #[serde(rename = "level")]
+    pub level_opt: Option<String>,
+    #[serde(rename = "targetLevels")]
+    pub target_levels_opt: Option<Vec<UiTargetLogLevel>>,
+}
+conversation_message!(UiLogLevelRequest, "logLevel");
+
+// The levels the Node is logging at afterward
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiLogLevelResponse {
+    pub level: String,
+    #[serde(rename = "targetLevels")]
+    pub target_levels: Vec<UiTargetLogLevel>,
+}
+conversation_message!(UiLogLevelResponse, "logLevel");
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiRotateLogRequest {}
+conversation_message!(UiRotateLogRequest, "rotateLog");
+
+// The path of the new compressed archive, or nothing if there was no log to rotate
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiRotateLogResponse {
+    #[serde(rename = "archive")]
+    pub archive_opt: Option<String>,
+}
+conversation_message!(UiRotateLogResponse, "rotateLog");
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiHealthRequest {}
+conversation_message!(UiHealthRequest, "health");
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiHealthCheck {
+    pub name: String,
+    pub passed: bool,
+    pub detail: String,
+}
+
+// healthy: every liveness check passed. ready: healthy, and every readiness check passed too.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiHealthResponse {
+    pub healthy: bool,
+    pub ready: bool,
+    pub checks: Vec<UiHealthCheck>,
+}
+conversation_message!(UiHealthResponse, "health");
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiCollectDiagnosticsRequest {}
+conversation_message!(UiCollectDiagnosticsRequest, "collectDiagnostics");
+
+// Where the Node put the tarball, on its own computer
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiCollectDiagnosticsResponse {
+    pub path: String,
+}
+conversation_message!(UiCollectDiagnosticsResponse, "collectDiagnostics");
+
+// The Node wrote a log record at or above the subscriber's level, from one of its targets
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiLogsBroadcast {
+    pub level: String,
+    pub target: String,
+    pub message: String,
+    pub timestamp: String,
+    #[serde(rename = "correlationId")]
+    pub correlation_id_opt: Option<String>,
+}
+fire_and_forget_message!(UiLogsBroadcast, "logs");
+
+// Public keys, in base64, of Nodes that just became or stopped being full neighbors
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiNeighborsChangedBroadcast {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+fire_and_forget_message!(UiNeighborsChangedBroadcast, "neighborsChanged");
+
+// direction is "sent" or "received"; amount is in gwei
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiPaymentBroadcast {
+    pub direction: String,
+    pub wallet: String,
+    pub amount: u64,
+    #[serde(rename = "transaction")]
+    pub transaction_opt: Option<String>,
+}
+fire_and_forget_message!(UiPaymentBroadcast, "payment");
+
+// Another Node says this one's consuming wallet owes it too much for too long, and that it will
+// stop serving this Node unless it's paid; balance is in gwei
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiPaymentOverdueBroadcast {
+    pub creditor: String,
+    pub debtor: String,
+    pub balance: i64,
+    #[serde(rename = "ageSeconds")]
+    pub age_seconds: u64,
+    #[serde(rename = "banInSeconds")]
+    pub ban_in_seconds: u64,
+}
+fire_and_forget_message!(UiPaymentOverdueBroadcast, "paymentOverdue");
+
+// An incoming payment to the earning wallet, as soon as the Node sees it on the blockchain; amount
+// is in gwei
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiPaymentReceivedBroadcast {
+    pub payer: String,
+    pub amount: u64,
+    pub transaction: String,
+    pub confirmations: u64,
+}
+fire_and_forget_message!(UiPaymentReceivedBroadcast, "paymentReceived");
+
+// No route could be found for a new stream
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiRouteFailureBroadcast {
+    pub hostname: String,
+}
+fire_and_forget_message!(UiRouteFailureBroadcast, "routeFailure");
+
+// A signed release manifest names a newer version than the one the Node is running
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiUpdateAvailableBroadcast {
+    #[serde(rename = "currentVersion")]
+    pub current_version: String,
+    #[serde(rename = "latestVersion")]
+    pub latest_version: String,
+    #[serde(rename = "releaseNotesUrl")]
+    pub release_notes_url: String,
+    pub security: bool,
+}
+fire_and_forget_message!(UiUpdateAvailableBroadcast, "updateAvailable");
+
+// How close a Neighborhood that can't route yet has got; progress is a percentage
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UiWarmUpBroadcast {
+    #[serde(rename = "nodesKnown")]
+    pub nodes_known: u64,
+    pub neighbors: u64,
+    #[serde(rename = "minHopsSatisfiable")]
+    pub min_hops_satisfiable: bool,
+    #[serde(rename = "routesAvailable")]
+    pub routes_available: bool,
+    pub progress: u8,
+}
+fire_and_forget_message!(UiWarmUpBroadcast, "warmUp");
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -812,4 +1599,37 @@ mod tests {
             ))
         );
     }
+
+    #[test]
+    fn setup_response_carries_a_code_for_each_parameter_in_error() {
+        let errors = ConfiguratorError::conflict(
+            &["neighborhood-mode", "neighbors"],
+            "Node cannot run as --neighborhood-mode zero-hop if --neighbors is specified",
+        )
+        .another_required("ip", "Invalid value: booga");
+
+        let subject = UiSetupResponse::new(false, HashMap::new(), errors, None);
+
+        let json = serde_json::to_value(&subject).unwrap();
+        assert_eq!(
+            json["errors"],
+            serde_json::json!([
+                {
+                    "parameter": "neighborhood-mode",
+                    "code": "Conflict",
+                    "message": "Node cannot run as --neighborhood-mode zero-hop if --neighbors is specified"
+                },
+                {
+                    "parameter": "neighbors",
+                    "code": "Conflict",
+                    "message": "Node cannot run as --neighborhood-mode zero-hop if --neighbors is specified"
+                },
+                {
+                    "parameter": "ip",
+                    "code": "Invalid",
+                    "message": "Invalid value: booga"
+                }
+            ])
+        );
+    }
 }
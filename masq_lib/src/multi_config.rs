@@ -1,7 +1,7 @@
 // Copyright (c) 2017-2019, Substratum LLC (https://substratum.net) and/or its affiliates. All rights reserved.
 
 use crate::command::StdStreams;
-use crate::shared_schema::{ConfiguratorError, ParamError};
+use crate::shared_schema::{ConfiguratorError, ParamError, ParamErrorCode};
 use crate::utils::exit_process;
 #[allow(unused_imports)]
 use clap::{value_t, values_t};
@@ -133,8 +133,9 @@ impl<'a> MultiConfig<'a> {
             let required_value_regex = Regex::new("--(.*?) ").expect("Bad regex");
             let mut requireds: Vec<ParamError> = vec![];
             while let Some(captures) = required_value_regex.captures(&remaining_message) {
-                requireds.push(ParamError::new(
+                requireds.push(ParamError::with_code(
                     &captures[1],
+                    ParamErrorCode::Required,
                     "ParamError parameter not provided",
                 ));
                 match remaining_message.find(&captures[1]) {
@@ -925,9 +926,18 @@ pub(crate) mod tests {
             .err()
             .unwrap();
 
-        let expected =
-            ConfiguratorError::required("another-arg", "ParamError parameter not provided")
-                .another_required("numeric-arg", "ParamError parameter not provided");
+        let expected = ConfiguratorError::new(vec![
+            ParamError::with_code(
+                "another-arg",
+                ParamErrorCode::Required,
+                "ParamError parameter not provided",
+            ),
+            ParamError::with_code(
+                "numeric-arg",
+                ParamErrorCode::Required,
+                "ParamError parameter not provided",
+            ),
+        ]);
         assert_eq!(result, expected);
     }
 
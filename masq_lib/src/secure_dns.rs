@@ -0,0 +1,196 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+
+use std::fmt;
+use std::net::Ipv6Addr;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SecureDnsProtocol {
+    // DNS-over-HTTPS (RFC 8484)
+    Https,
+    // DNS-over-TLS (RFC 7858)
+    Tls,
+}
+
+// An encrypted DNS upstream, written https://host[:port][/path] or tls://host[:port]
+#[derive(Clone, Debug, PartialEq)]
+pub struct SecureDnsServer {
+    pub protocol: SecureDnsProtocol,
+    pub host: String,
+    pub port: u16,
+    pub path: String,
+}
+
+impl FromStr for SecureDnsServer {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (protocol, remainder) = if let Some(remainder) = s.strip_prefix("https://") {
+            (SecureDnsProtocol::Https, remainder)
+        } else if let Some(remainder) = s.strip_prefix("tls://") {
+            (SecureDnsProtocol::Tls, remainder)
+        } else {
+            return Err(format!(
+                "Secure DNS server '{}' must start with https:// or tls://",
+                s
+            ));
+        };
+        let (authority, path) = match remainder.find('/') {
+            Some(idx) => (&remainder[..idx], &remainder[idx..]),
+            None => (remainder, ""),
+        };
+        let (host, port_str_opt) = Self::split_authority(authority)
+            .ok_or_else(|| format!("Secure DNS server '{}' has a malformed host", s))?;
+        if host.is_empty() {
+            return Err(format!("Secure DNS server '{}' has no host", s));
+        }
+        let port = match port_str_opt {
+            None => match protocol {
+                SecureDnsProtocol::Https => 443,
+                SecureDnsProtocol::Tls => 853,
+            },
+            Some(port_str) => match u16::from_str(port_str) {
+                Ok(port) if port > 0 => port,
+                _ => return Err(format!("Secure DNS server '{}' has an invalid port", s)),
+            },
+        };
+        let path = match (protocol, path) {
+            (SecureDnsProtocol::Https, "") => "/dns-query".to_string(),
+            (SecureDnsProtocol::Https, path) => path.to_string(),
+            (SecureDnsProtocol::Tls, "") | (SecureDnsProtocol::Tls, "/") => String::new(),
+            (SecureDnsProtocol::Tls, _) => {
+                return Err(format!(
+                    "Secure DNS server '{}' cannot have a path: only https:// servers can",
+                    s
+                ))
+            }
+        };
+        Ok(SecureDnsServer {
+            protocol,
+            host: host.to_string(),
+            port,
+            path,
+        })
+    }
+}
+
+impl fmt::Display for SecureDnsServer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let scheme = match self.protocol {
+            SecureDnsProtocol::Https => "https",
+            SecureDnsProtocol::Tls => "tls",
+        };
+        match Ipv6Addr::from_str(&self.host) {
+            Ok(_) => write!(f, "{}://[{}]:{}{}", scheme, self.host, self.port, self.path),
+            Err(_) => write!(f, "{}://{}:{}{}", scheme, self.host, self.port, self.path),
+        }
+    }
+}
+
+impl SecureDnsServer {
+    // IPv6 hosts come in brackets, so that their colons aren't mistaken for the port separator
+    fn split_authority(authority: &str) -> Option<(&str, Option<&str>)> {
+        if authority.starts_with('[') {
+            let close = authority.find(']')?;
+            let host = &authority[1..close];
+            Ipv6Addr::from_str(host).ok()?;
+            match &authority[(close + 1)..] {
+                "" => Some((host, None)),
+                rest if rest.starts_with(':') => Some((host, Some(&rest[1..]))),
+                _ => None,
+            }
+        } else {
+            match authority.rfind(':') {
+                Some(idx) if !authority[..idx].contains(':') => {
+                    Some((&authority[..idx], Some(&authority[(idx + 1)..])))
+                }
+                Some(_) => None,
+                None => Some((authority, None)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secure_dns_servers_are_parsed_with_defaults() {
+        assert_eq!(
+            SecureDnsServer::from_str("https://cloudflare-dns.com"),
+            Ok(SecureDnsServer {
+                protocol: SecureDnsProtocol::Https,
+                host: "cloudflare-dns.com".to_string(),
+                port: 443,
+                path: "/dns-query".to_string(),
+            })
+        );
+        assert_eq!(
+            SecureDnsServer::from_str("https://9.9.9.9:5053/custom/path"),
+            Ok(SecureDnsServer {
+                protocol: SecureDnsProtocol::Https,
+                host: "9.9.9.9".to_string(),
+                port: 5053,
+                path: "/custom/path".to_string(),
+            })
+        );
+        assert_eq!(
+            SecureDnsServer::from_str("tls://1.1.1.1"),
+            Ok(SecureDnsServer {
+                protocol: SecureDnsProtocol::Tls,
+                host: "1.1.1.1".to_string(),
+                port: 853,
+                path: String::new(),
+            })
+        );
+        assert_eq!(
+            SecureDnsServer::from_str("tls://[2606:4700:4700::1111]:8853"),
+            Ok(SecureDnsServer {
+                protocol: SecureDnsProtocol::Tls,
+                host: "2606:4700:4700::1111".to_string(),
+                port: 8853,
+                path: String::new(),
+            })
+        );
+    }
+
+    #[test]
+    fn bad_secure_dns_servers_are_rejected() {
+        let check = |s: &str, msg: &str| {
+            assert_eq!(
+                SecureDnsServer::from_str(s),
+                Err(format!("Secure DNS server '{}' {}", s, msg))
+            )
+        };
+
+        check("1.1.1.1", "must start with https:// or tls://");
+        check("http://1.1.1.1", "must start with https:// or tls://");
+        check("https://", "has no host");
+        check("https://:443", "has no host");
+        check("tls://1.1.1.1:booga", "has an invalid port");
+        check("tls://1.1.1.1:0", "has an invalid port");
+        check("tls://2606:4700::1111", "has a malformed host");
+        check("tls://[booga]:853", "has a malformed host");
+        check(
+            "tls://1.1.1.1/dns-query",
+            "cannot have a path: only https:// servers can",
+        );
+    }
+
+    #[test]
+    fn secure_dns_servers_survive_a_round_trip_through_a_string() {
+        vec![
+            "https://dns.google:443/dns-query",
+            "tls://1.1.1.1:853",
+            "tls://[2606:4700:4700::1111]:853",
+        ]
+        .into_iter()
+        .for_each(|s| {
+            let server = SecureDnsServer::from_str(s).unwrap();
+
+            assert_eq!(server.to_string(), s.to_string());
+            assert_eq!(SecureDnsServer::from_str(&server.to_string()), Ok(server));
+        });
+    }
+}
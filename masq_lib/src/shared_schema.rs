@@ -1,11 +1,19 @@
 use crate::constants::{
     DEFAULT_CHAIN_NAME, DEFAULT_GAS_PRICE, DEFAULT_UI_PORT, HIGHEST_USABLE_PORT,
-    LOWEST_USABLE_INSECURE_PORT,
+    LOWEST_USABLE_INSECURE_PORT, MAX_THREAD_COUNT,
 };
 use crate::crash_point::CrashPoint;
+use crate::utils::MAX_PORT_LIST_LENGTH;
 use clap::{App, Arg};
 use lazy_static::lazy_static;
-
+use serde_derive::{Deserialize, Serialize};
+
+pub const BANDWIDTH_BENCHMARK_HELP: &str =
+    "Whether your Node should measure the latency and bandwidth of its links to its neighbors, by timing small \
+     probes sent across them every few minutes, and publish the results so that Nodes choosing routes can favor \
+     fast links over slow ones. Only neighbors that have turned this on too are measured, and your Node answers \
+     probes only when it's on. --bandwidth-benchmark is meaningless in --neighborhood-mode zero-hop. The default \
+     is off.";
 pub const BLOCKCHAIN_SERVICE_HELP: &str =
     "The Ethereum client you wish to use to provide Blockchain \
      exit services from your MASQ Node (e.g. http://localhost:8545, \
@@ -13,6 +21,21 @@ pub const BLOCKCHAIN_SERVICE_HELP: &str =
 pub const CHAIN_HELP: &str =
     "The blockchain network MASQ Node will configure itself to use. You must ensure the \
     Ethereum client specified by --blockchain-service-url communicates with the same blockchain network.";
+pub const CLANDESTINE_KEY_LIFETIME_HELP: &str =
+    "How many days your Node should use the same clandestine key before replacing it with a new one. \
+     Ordinarily your Node makes up a new key every time it starts; with this parameter, it keeps its key \
+     in the database instead, so it requires --db-password. When the key is replaced, the old one signs \
+     the new one so that other Nodes can tell it's still you, and both keys work for a while afterward \
+     (see --clandestine-key-overlap).";
+pub const CLANDESTINE_KEY_OVERLAP_HELP: &str =
+    "How many hours your Node should keep accepting traffic for its old clandestine key after replacing \
+     it with a new one, so that Nodes that haven't heard about the new key yet can still reach you. \
+     The default is 24. Has no effect without --clandestine-key-lifetime.";
+pub const CLOCK_SKEW_TOLERANCE_HELP: &str =
+    "How many seconds this computer's clock may disagree with other Nodes' clocks and with network time \
+     before it matters. CORES packages stamped further than this from your Node's clock, either way, are \
+     refused as possible replays, and a difference from network time bigger than this is logged as a warning \
+     (see --time-servers). The default is 120.";
 pub const CONFIG_FILE_HELP: &str =
     "Optional TOML file containing configuration that doesn't often change. Should contain only \
      scalar items, string or numeric, whose names are exactly the same as the command-line parameters \
@@ -27,6 +50,21 @@ pub const CONSUMING_PRIVATE_KEY_HELP: &str = "The private key for the Ethereum w
      make sure you haven't already set up a consuming wallet with a derivation path, and make sure that you always \
      supply exactly the same private key every time you run the Node. A consuming private key is 64 case-insensitive \
      hexadecimal digits.";
+pub const CPU_AFFINITY_HELP: &str =
+    "The CPU cores your Node should run on, numbered from 0, as a comma-separated list of cores and \
+     ranges of cores: for example, 0,1 or 2-5,7. If you're running other services on a small computer, \
+     pinning your Node to a core or two keeps it from crowding them out. If you don't specify any cores, \
+     your Node runs wherever the operating system puts it. Works only on Linux.";
+pub const DATA_CAP_HELP: &str =
+    "The number of gigabytes a month your Node may spend routing data and carrying it as an exit for other \
+     Nodes, for when your Internet connection is metered. When 90% of it is gone, your Node stops taking on \
+     new exit streams; when all of it is gone, your Node stops serving other Nodes entirely until the next \
+     month begins. Your own browsing isn't limited. If you don't specify a cap, there's no limit.";
+pub const DATA_CAP_RATES_HELP: &str =
+    "Times of day when data counts for more or less than usual against --data-cap, as a comma-separated list \
+     of <FROM-HOUR>-<TO-HOUR>:<PERCENT> entries in local time. For example, 0-6:0 makes data free between \
+     midnight and six in the morning, and 18-23:200 makes evening data count double. The first entry that \
+     covers an hour applies to it; hours no entry covers count at 100%.";
 pub const DATA_DIRECTORY_HELP: &str =
     "Directory in which the Node will store its persistent state, including at \
      least its database and by default its configuration file as well.";
@@ -34,29 +72,135 @@ pub const DB_PASSWORD_HELP: &str =
     "A password or phrase to decrypt the encrypted material in the database, to include your \
      mnemonic seed (if applicable) and your list of previous neighbors. If you don't provide this \
      password, none of the encrypted data in your database will be used.";
+pub const DESCRIPTOR_SIGNATURE_HELP: &str =
+    "Your signature of this Node's descriptor, made with your earning wallet's key: sign the descriptor the \
+     Node prints at startup, exactly as printed, with your wallet's \"sign message\" feature. When the signature \
+     fits, the Node prints the descriptor again with your earning wallet and the signature appended, ready to \
+     publish, so that people who use it in --neighbors can be sure it's yours. Sign again whenever your IP \
+     address changes. There is no default.";
+pub const DNS_CACHE_SIZE_HELP: &str =
+    "How many answers to its look-ups your Node remembers while providing exit services, so that new streams \
+     to popular hosts don't wait for a DNS server. Answers are remembered for as long as their time-to-live \
+     allows, including answers that a host doesn't exist; the one used least recently makes room when the \
+     cache is full. The default is 1000; specify 0 to turn the cache off.";
+pub const DNS_LOOKUP_THREADS_HELP: &str =
+    "How many threads your Node gives to look-ups through --secure-dns-servers while providing exit services. \
+     Each one waits for a server to answer, so more of them let more new streams get their addresses at once. \
+     The default is 4; you can specify from 1 to 64.";
 pub const DNS_SERVERS_HELP: &str =
     "IP addresses of DNS Servers for host name look-up while providing exit \
      services for other MASQ Nodes (e.g. 1.0.0.1,1.1.1.1,8.8.8.8,9.9.9.9, etc.)";
+pub const DNS_PORT_HELP: &str =
+    "The port on 127.0.0.1 where the Node's own DNS server answers look-ups from this computer. The default \
+     is 53, where your system resolver expects to find a DNS server; choose another if something else \
+     already has port 53, and send your look-ups there yourself (from a local resolver, for instance).";
+pub const ENCRYPTED_SNI_HELP: &str =
+    "What your Node should do with a TLS connection whose client hides the name of the server it wants, \
+     using an encrypted SNI, so that there's no telling where the connection should go. With refuse, the \
+     client gets a TLS alert; with prompt, the connection waits until you tell masq where to send it; with a \
+     host name, every such connection goes to that host, which should be a server that can decrypt the \
+     real name, such as the one the client got its encryption keys from. The default is refuse. An \
+     encrypted client hello (ECH) names a public server in the clear, and goes there without any of this.";
 pub const EARNING_WALLET_HELP: &str =
     "An Ethereum wallet address. Addresses must begin with 0x followed by 40 hexadecimal digits \
      (case-insensitive). If you already have a derivation-path earning wallet, don't supply this. \
      If you have supplied an earning wallet address before, either don't supply it again or be \
-     careful to supply exactly the same one you supplied before.";
+     careful to supply exactly the same one you supplied before, unless you mean to change it with \
+     --earning-wallet-change on.";
+pub const EARNING_WALLET_CHANGE_HELP: &str =
+    "Whether the --earning-wallet you supply may replace the one you supplied before. When it does, your Node \
+     announces the change, with your --earning-wallet-change-signature, to the rest of the Network, so that \
+     other Nodes start paying what they owe you to the new wallet from the next block on. Don't turn this on \
+     unless you mean to stop earning into the old wallet. The default is off.";
+pub const EARNING_WALLET_CHANGE_SIGNATURE_HELP: &str =
+    "Your signature, made with the key of the earning wallet you're changing from, of the message \
+     \"Pay what is owed to <old wallet> to <new wallet> instead\", using your wallet's \"sign message\" \
+     feature. Other Nodes move what they owe the old wallet to the new one only if the old wallet signed for \
+     it. Required with --earning-wallet-change on when the wallet changes; if it's missing, the Node tells \
+     you the exact message to sign. There is no default.";
+pub const EXIT_POLICY_HELP: &str =
+    "The traffic your Node will refuse to carry out to the Internet while providing exit services for other \
+     MASQ Nodes. Deny single ports like port:25, port ranges like port:6881-6889, host names (with all their \
+     subdomains) like host:example.com, and standard categories like category:mail, category:file-sharing, \
+     category:irc, or category:tor. Separate them with commas (but no spaces). Your Node advertises this \
+     policy to the rest of the Network, so other Nodes will route such traffic through different exit Nodes.";
+pub const DUMP_NEIGHBORHOOD_HELP: &str =
+    "Whether your Node should keep neighborhood.json and neighborhood.dot in the data directory up to date with the \
+     Nodes it knows of and how they're connected, every time Gossip changes its picture of the Network, so that you \
+     can look at the graph while you're chasing a routing failure. Keys are cut short and IP addresses left out, \
+     as in the neighborhoodGraph UI message. The default is off.";
+pub const FAUCET_URL_HELP: &str =
+    "The https:// URL of a faucet that gives test funds away on the testnet in --chain. With --request-test-funds, \
+     or when a UI asks, your Node posts its consuming wallet's address there and reports the transaction the faucet \
+     sends the funds in. There's no faucet for mainnet.";
+pub const GOSSIP_RECORDING_HELP: &str =
+    "Whether your Node should record the Gossip it receives, with every public key and IP address replaced by a \
+     made-up one, into gossip-recording.cbor in the data directory, so that you can send the file with a report \
+     about your Node's neighborhood going wrong. The file is started over each time the Node starts. The default \
+     is off.";
+pub const HEED_ATTESTATIONS_HELP: &str =
+    "Whether your Node should give a wallet it has never been paid by, or stiffed by, as much time to pay \
+     when it falls behind as other Nodes' attestations say it deserves (see --publish-attestations). Each \
+     earning wallet attesting counts only once, for no more than 10 payments each way, and only if your Node \
+     has no reason to distrust it; a wallet your Node has a payment record of its own for is judged by that \
+     record alone. The default is off.";
+pub const HOP_TIMING_HELP: &str =
+    "Whether your Node should ask the Nodes on the way back from each exit to note, encrypted so that only your \
+     Node can read it, when they pass a response on, so that the diagnostics bundle can show which hop of a slow \
+     route is slow. The per-hop figures compare the clocks of different computers, so they're only as good as \
+     those clocks are synchronized. --hop-timing is meaningless in --neighborhood-mode zero-hop. The default is \
+     off.";
 pub const IP_ADDRESS_HELP: &str = "The public IP address of your MASQ Node: that is, the IPv4 \
      address at which other Nodes can contact yours. If you're running your Node behind \
      a router, this will be the IP address of the router. If this IP address starts with 192.168 or 10.0, \
      it's a local address rather than a public address, and other Nodes won't be able to see yours. \
      --ip is meaningless except in --neighborhood-mode standard.";
+pub const LOCAL_DISCOVERY_HELP: &str =
+    "Whether your Node should announce itself to, and look for, other MASQ Nodes on your local network using \
+     multicast DNS. When two Nodes on the same network find each other this way, they introduce themselves \
+     directly, so they can peer without anyone copying descriptors between them. Your Node's descriptor is public \
+     anyway, but anyone on your local network will be able to see that a MASQ Node is running there. \
+     --local-discovery is meaningless except in --neighborhood-mode standard. The default is off.";
+pub const LOCAL_PROXY_HELP: &str =
+    "Run your Node as a proxy for this computer alone, for testing applications against its proxy and DNS \
+     machinery: it runs in --neighborhood-mode zero-hop, sends everything straight to its destination, and \
+     neither earns nor pays, so it needs no wallets and no --db-password. --local-proxy by itself means on; \
+     it can't be combined with --neighbors, --ip, or a --neighborhood-mode other than zero-hop. The default is \
+     off.";
 pub const LOG_LEVEL_HELP: &str =
     "The minimum severity of the logs that should appear in the Node's logfile. You should probably not specify \
      a level lower than the default unless you're doing testing or forensics: a Node at the 'trace' log level \
      generates a lot of log traffic. This will both consume your disk space and degrade your Node's performance. \
      You should probably not specify a level higher than the default unless you have security concerns about \
      persistent logs being kept on your computer: if your Node crashes, it's good to know why.";
+pub const LOG_REDACTION_HELP: &str =
+    "Whether wallet addresses, node descriptors, IP addresses of other Nodes, derivation paths and the like \
+     should be replaced with <redacted> in the Node's log and in its panic messages, so that a log can be \
+     shared without giving them away. Turn it off only if you're a developer who needs to see them. The \
+     default is on.";
+pub const MASQUERADE_PROFILES_HELP: &str =
+    "The ways besides the standard JSON-like one that your Node should ask other Nodes to disguise the data they \
+     send it, most preferred first among websocket, separated by commas (but no spaces). Your Node understands \
+     every way whatever you say here, and sends each Node its data in the one it asked for. If you leave this \
+     out, other Nodes send it the standard one.";
+pub const METRICS_PORT_HELP: &str =
+    "The port on 127.0.0.1 where your Node should serve counters and gauges about its work, such as bytes \
+     routed, open streams, neighbors, and what it owes, for Prometheus to scrape at /metrics. If you don't \
+     specify a port, there's no metrics endpoint. Only programs on this computer can reach it; if your \
+     Prometheus server is elsewhere, run an agent or a tunnel here to carry the metrics to it.";
+pub const MULTIPATH_HELP: &str =
+    "Whether your Node should send the data of each stream it originates over a second route to the same \
+     exit Node, through relay Nodes the first route doesn't use. With stripe, consecutive packets alternate \
+     between the two routes, for more throughput; with duplicate, every packet goes over both, so that a \
+     download keeps going when one route slows down or breaks. Duplicating doubles what you pay for routing \
+     and exit services. The default is off. A stream stays on one route if no second route can be found.";
 pub const NEIGHBORS_HELP: &str = "One or more Node descriptors for running Nodes in the MASQ \
      Network to which you'd like your Node to connect on startup. A Node descriptor looks like \
      this:\n\ngBviQbjOS3e5ReFQCvIhUM3i02d1zPleo1iXg/EN6zQ:86.75.30.9:5542 (initial ':' for testnet) and\n\
      gBviQbjOS3e5ReFQCvIhUM3i02d1zPleo1iXg/EN6zQ@86.75.30.9:5542 (initial '@' for mainnet)\n\n\
+     A descriptor its operator has signed has their earning wallet and signature appended, like \
+     this:\n\n<descriptor>#<earning wallet>#<signature>\n\nand is refused unless that wallet signed it; \
+     make sure the wallet is the one its operator publishes. \
      If you have more than one, separate them with commas (but no spaces). There is no default value; \
      if you don't specify a neighbor, your Node will start without being connected to any MASQ \
      Network, although other Nodes will be able to connect to yours if they know your Node's descriptor. \
@@ -80,12 +224,195 @@ pub const NEIGHBORHOOD_MODE_HELP: &str = "This configures the way the Node relat
      standard means that your Node will operate fully unconstrained, both originating and accepting \
      connections, both consuming and providing services, and when you operate behind a router, it \
      requires that you forward your clandestine port through that router to your Node's machine.";
+pub const OUTPUT_HELP: &str =
+    "How the modes that do their job and stop, like --dump-config, --generate-wallet, --recover-wallet \
+     and --collect-diagnostics, should report what they did. With json, they write one JSON object to \
+     standard output, carrying the version of its layout and either the result or the error, so that scripts \
+     and UIs don't have to pick apart text meant for people. The default is human.";
+pub const PASSWORD_PROMPT_HELP: &str =
+    "How your Node asks for the secrets you didn't give it: tty prompts at the console; pinentry shows a dialog \
+     from the pinentry program on your PATH, as GPG does; environment takes them from environment variables \
+     without asking anyone, either directly (MASQ_DB_PASSWORD) or from the first line of the file a variable \
+     names (MASQ_DB_PASSWORD_FILE). Defaults to tty.";
+pub const PAYMENT_TOKEN_HELP: &str =
+    "The ERC-20 token your Node settles its debts in, if your mesh doesn't use MASQ: its symbol, its contract \
+     address, and how many decimals it has, separated by colons (e.g. USDC:0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48:6). \
+     Every Node you trade with must settle in the same token. The database keeps its accounts in the token it \
+     was first used with, so give each token its own --data-directory. The default is the chain's MASQ token.";
+pub const POST_QUANTUM_HELP: &str =
+    "Whether your Node should also use a Kyber key when it exchanges keys with other Nodes that have \
+     this turned on too, so that someone who records your traffic today can't read it once quantum computers \
+     can break the ordinary keys. Turning it on adds about a kilobyte to everything encrypted for such a Node, \
+     and to your Node's record in Gossip. With --clandestine-key-lifetime, the Kyber key is kept in the \
+     database along with the clandestine key. The default is off.";
+pub const PORT_RESELECTION_HELP: &str =
+    "Whether your Node should move its clandestine port somewhere else if another program is already using \
+     it when the Node starts, instead of refusing to start. The new port is kept in the database and used from \
+     then on, just as if you'd specified it with --clandestine-port. The UI port and the HTTP and TLS ports are \
+     never moved, since other software expects to find them where they are. The default is off.";
+pub const PRICE_FEED_URL_HELP: &str =
+    "The https:// URL of a price feed that answers with JSON like {\"price\": \"0.0421\"}: the price of one MASQ \
+     token, in whatever currency the feed quotes. Once a day your Node records its earning wallet's balances in \
+     its database for year-end reporting, and with this it records the price along with them. Without it, the \
+     balances are recorded alone.";
+pub const PROTOCOL_SNIFFING_HELP: &str =
+    "Whether your Node should keep the first 512 bytes of each of the last 100 streams from your computer in \
+     which it couldn't find a host to send them to, so that a bug report about a program that doesn't work \
+     through the Node can show what that program actually sends. They're kept in the Node's memory only, and \
+     they're never routed anywhere; a UI's collectDiagnostics request puts them in the diagnostics bundle as \
+     JSON and as a pcap file that Wireshark can read. The default is off.";
+pub const PROXIED_DOMAINS_HELP: &str =
+    "The domains (with all their subdomains) whose traffic the Node should carry, like \
+     example.com,example.org: the Node's DNS server answers look-ups for them with the Node's own address \
+     and passes every other look-up on to the first of your --dns-servers, so that the rest of your \
+     traffic goes straight to the Internet. Separate them with commas (but no spaces). If you leave this \
+     out, every look-up is answered with the Node's own address, so all your traffic goes through the Node.";
 pub const REAL_USER_HELP: &str =
     "The user whose identity Node will assume when dropping privileges after bootstrapping. Since Node refuses to \
      run with root privilege after bootstrapping, you might want to use this if you start the Node as root, or if \
      you start the Node using pkexec or some other method that doesn't populate the SUDO_xxx variables. Use a value \
      like <uid>:<gid>:<home directory>.";
 
+pub const PUBLISH_ATTESTATIONS_HELP: &str =
+    "Whether your Node should tell the rest of the Network, once an hour in its signed Gossip, how many times \
+     each of the (at most 16) wallets that have owed it the most payments paid on time and how many times it \
+     paid late. Other Nodes may use this to decide how much credit to give those wallets; see \
+     --heed-attestations. The default is off.";
+pub const REPUTATION_LISTS_HELP: &str =
+    "The DNS blocklists your Node should look its own public IP address up in, like \
+     zen.example.org,bl.example.net, when it starts and every six hours after that, if it routes data for \
+     other Nodes. Sites often refuse or challenge visitors from listed addresses, so your Node warns you if \
+     any list has yours, and tells other Nodes in Gossip what percentage of the lists don't. Separate them \
+     with commas (but no spaces). If you leave this out, there's no check.";
+pub const ROUTE_SEARCH_THREADS_HELP: &str =
+    "How many threads your Node may use at once to look for routes through a big neighborhood. Neighborhoods \
+     of fewer than a thousand Nodes are searched on one thread no matter what. The default is 4; you can \
+     specify from 1 to 64.";
+pub const SANDBOX_HELP: &str =
+    "Whether your Node should give up every privilege it has no more use for once it has bound its ports and \
+     dropped to the real user: on Linux, it drops all its capabilities and has the kernel refuse it system calls \
+     like ptrace and mount that a Node never needs; on Windows, it disables every privilege in its token. Since the \
+     Node holds your wallets' keys and talks to strangers, this limits what anyone who managed to subvert it could \
+     do to your machine. If the sandbox can't be set up, the Node warns you and runs without it. Use off only if \
+     the sandbox gets in the way of something on your system. The default is on.";
+pub const SECURE_DNS_SERVERS_HELP: &str =
+    "Encrypted DNS servers to use instead of --dns-servers for host name look-up while providing exit services \
+     for other MASQ Nodes, so that your ISP can't watch the look-ups your Node does on other people's behalf. \
+     Specify DNS-over-HTTPS servers like https://cloudflare-dns.com/dns-query and DNS-over-TLS servers like \
+     tls://1.1.1.1:853; the port and the /dns-query path are the defaults and can be left out. Separate them \
+     with commas (but no spaces). They're tried in order, and if none of them can be reached, the look-up fails: \
+     the Node never falls back to unencrypted DNS while secure DNS servers are configured. A server specified \
+     by name will have its own address looked up by your system resolver, so use IP addresses if you want to \
+     avoid even that.";
+
+pub const SOCKS_CREDENTIALS_HELP: &str =
+    "The username and password SOCKS clients must supply to use the SOCKS listener, separated by a colon: \
+     for example, myname:mypassword. If you leave this out, SOCKS clients won't need to authenticate, so \
+     anyone who can reach your --socks-port can use your Node. The username can't contain a colon, and \
+     neither the username nor the password may be longer than 255 bytes. Like any password, this is \
+     better supplied in the environment than on the command line.";
+
+pub const STREAM_IDLE_TIMEOUT_HELP: &str =
+    "The number of seconds a TCP stream your Node opens to a server while providing exit services may go \
+     without carrying any data in either direction before your Node closes it. Leave this out, or specify 0, \
+     for no idle timeout at all; either way, your Node sends TCP keepalives on its connections so that \
+     routers and firewalls along the way don't silently forget quiet ones, such as IMAP IDLE sessions or \
+     websockets.";
+pub const TIME_SERVERS_HELP: &str =
+    "NTP servers your Node asks for the time when it starts and every hour afterward, to find out whether this \
+     computer's clock is wrong, like pool.ntp.org,time.cloudflare.com:123; port 123 is the default. Separate them \
+     with commas (but no spaces). The first one that answers is believed. Your Node never sets the clock itself: \
+     if the clock is off by more than --clock-skew-tolerance, it logs a warning saying by how much. Specify off to \
+     skip the check. The default is pool.ntp.org,time.cloudflare.com.";
+pub const GEO_DB_URL_HELP: &str =
+    "The https URL of a mirror your Node should download its geolocation (GeoIP and ASN) database from, \
+     and keep it up to date from now and then. The mirror must also serve the Ed25519 signature of the \
+     database, in hexadecimal, at the same URL with .sig on the end; a database without the right \
+     signature is thrown away. If you don't specify a URL, your Node never downloads one. Requires \
+     --geo-db-key.";
+pub const GEO_DB_KEY_HELP: &str =
+    "The Ed25519 public key, in 64 hexadecimal digits, whose signature a geolocation database must bear \
+     before your Node uses it. Get it from the same people who run the mirror.";
+pub const GEO_DB_INTERVAL_HELP: &str =
+    "How many hours your Node should wait between downloads of the geolocation database. The default is \
+     168: once a week.";
+pub const UPDATE_CHECK_URL_HELP: &str =
+    "The https URL of a signed release manifest your Node should check now and then to find out whether \
+     there's a newer release than the one it's running. If there is, it logs a warning and tells any UIs \
+     that are connected, with a link to the release notes. If you don't specify a URL, your Node never \
+     checks. Requires --update-check-key.";
+pub const UPDATE_CHECK_KEY_HELP: &str =
+    "The Ed25519 public key, in 64 hexadecimal digits, whose signature a release manifest must bear before \
+     your Node believes it. Get it from the same people who publish the manifest; a manifest that isn't \
+     signed with it is ignored.";
+pub const UPDATE_CHECK_INTERVAL_HELP: &str =
+    "How many hours your Node should wait between checks for a newer release. The default is 24.";
+pub const UI_TLS_HELP: &str =
+    "Whether user interfaces must connect over TLS (wss:// rather than ws://). With on, the UI port \
+     listens on every network interface instead of just localhost, so that a GUI on another machine can \
+     manage this one, and nothing that isn't encrypted is accepted except from localhost. The \
+     certificate comes from --ui-tls-certificate and --ui-tls-key if you supply them; otherwise a \
+     self-signed one is made the first time and kept in the data directory, and its fingerprint is \
+     logged so that you can tell your GUI to trust it. Use --ui-interfaces, --ui-allowlist and \
+     --ui-tls-client-ca to narrow down who can get in. The default is off.";
+pub const UI_TLS_CERTIFICATE_HELP: &str =
+    "The path of a PEM file containing the certificate, followed by any intermediate certificates, that \
+     the UI port should present when --ui-tls is on. Must be supplied with --ui-tls-key.";
+pub const UI_TLS_KEY_HELP: &str =
+    "The path of a PEM file containing the private key that goes with --ui-tls-certificate. Keep it \
+     readable only by the user the Node runs as.";
+pub const UI_INTERFACES_HELP: &str =
+    "A comma-separated list of the IP addresses of the network interfaces the UI port should listen on \
+     when --ui-tls is on, for example 10.8.0.1 to let in only UIs that come through a VPN. It listens on \
+     localhost too, whatever you say here. Leave it out to listen on every interface.";
+pub const UI_ALLOWLIST_HELP: &str =
+    "A comma-separated list of the networks, in CIDR notation (for example 203.0.113.7 or \
+     192.168.0.0/24), that UIs on other machines may connect from when --ui-tls is on. Connections from \
+     anywhere else are dropped before anything is said to them; connections from localhost are always \
+     accepted. Leave it out to accept connections from anywhere.";
+pub const UI_TLS_CLIENT_CA_HELP: &str =
+    "The path of a PEM file containing the certificates of the certificate authorities that UIs' own \
+     certificates must be signed by. If you specify it, the UI port insists that anything connecting over \
+     TLS present a certificate of its own (mutual TLS), so that a stolen UI password isn't enough to get \
+     in from another machine. Only means anything when --ui-tls is on.";
+pub const UI_PASSWORD_HELP: &str =
+    "A password that user interfaces must present before they can do anything at all through the UI \
+     port. Presenting it gives a UI full control. If you don't specify it, anything that can reach the \
+     UI port controls your Node. Five wrong passwords in a row lock the address they came from out for \
+     five minutes.";
+pub const UI_READ_ONLY_PASSWORD_HELP: &str =
+    "A second password for user interfaces that should be able to look at your Node but not change it: \
+     they can see its status, its neighborhood, its finances and its streams, but cannot set it up, \
+     start it, or shut it down. Must be supplied with --ui-password.";
+pub const UI_REST_PORT_HELP: &str =
+    "The port on 127.0.0.1 where your Node should answer plain HTTP requests for its status, its \
+     financials, and its application and split-tunnel rules, and for it to shut down, so that scripts \
+     can manage it without speaking WebSockets. If you don't specify a port, there's no REST endpoint. \
+     If you've set --ui-password, requests must carry it in an 'Authorization: Bearer' header.";
+pub const UI_GRPC_PORT_HELP: &str =
+    "The port on 127.0.0.1 where your Node should serve gRPC calls for its status and financials, and for it to \
+     shut down, so that controllers can use stubs generated from proto/masq_ui.proto instead of speaking \
+     WebSockets. (The Daemon's --ui-grpc-port serves setup.) If you don't specify a port, there's no gRPC \
+     endpoint. If you've set --ui-password, calls must carry it in 'authorization: Bearer' metadata.";
+pub const HEALTH_PORT_HELP: &str =
+    "A port on which your Node should answer plain HTTP health checks from orchestrators like Kubernetes: \
+     GET /health says whether its actors are responsive and its database is reachable, and GET /ready \
+     says whether it's also connected to the MASQ Network with routes available. Each answers 200 if so \
+     and 503 if not. The port listens on every interface and reveals nothing but how the checks went. \
+     If you don't specify a port, there are no health endpoints, but UIs can still ask through the UI port.";
+pub const LOG_ROTATION_SIZE_HELP: &str =
+    "How big, in megabytes, your Node's log may grow before it's compressed into an archive and started \
+     over. Zero means the log is never rotated for its size. If you don't specify it, the Node uses what \
+     you specified last time, or 100 megabytes if you never have.";
+pub const LOG_ROTATION_AGE_HELP: &str =
+    "How many hours your Node should write to its log before compressing it into an archive and starting \
+     over, however big it is. Zero means the log is never rotated for its age. If you don't specify it, \
+     the Node uses what you specified last time, or zero if you never have.";
+pub const LOG_RETENTION_HELP: &str =
+    "How many compressed log archives your Node should keep in its data directory. When there are more, \
+     the oldest are deleted. If you don't specify it, the Node uses what you specified last time, or 50 \
+     if you never have.";
+
 lazy_static! {
     pub static ref DEFAULT_UI_PORT_VALUE: String = DEFAULT_UI_PORT.to_string();
     pub static ref UI_PORT_HELP: String = format!(
@@ -102,6 +429,23 @@ lazy_static! {
          Must be between {} and {} [default: last used port]",
         LOWEST_USABLE_INSECURE_PORT, HIGHEST_USABLE_PORT
     );
+    pub static ref ADDITIONAL_CLANDESTINE_PORTS_HELP: String = format!(
+        "Further ports this Node will listen on and advertise to other Nodes for clandestine traffic, \
+         alongside --clandestine-port: useful if some of your potential neighbors sit behind firewalls \
+         that only allow certain ports through. Specify a comma-separated list of ports and port ranges \
+         with no spaces, such as 8443,9000-9005. Unlike --clandestine-port, these ports are not \
+         remembered between runs. --additional-clandestine-ports is meaningless except in \
+         --neighborhood-mode standard. Each port must be between {} and {}, and you can name at most {} \
+         ports altogether.",
+        LOWEST_USABLE_INSECURE_PORT, HIGHEST_USABLE_PORT, MAX_PORT_LIST_LENGTH
+    );
+    pub static ref SOCKS_PORT_HELP: String = format!(
+        "The port at which the Node will accept SOCKS5 connections, so that applications can be pointed \
+         at the Node explicitly instead of having their traffic redirected to it. Only the CONNECT command \
+         is supported. If you leave this out, the Node won't accept SOCKS connections at all. \
+         Must be between {} and {}.",
+        LOWEST_USABLE_INSECURE_PORT, HIGHEST_USABLE_PORT
+    );
     pub static ref GAS_PRICE_HELP: String = format!(
        "The Gas Price is the amount of Gwei you will pay per unit of gas used in a transaction. \
        If left unspecified, MASQ Node will use the previously stored value (Default {}). Valid range is 1-99 Gwei.",
@@ -152,6 +496,48 @@ pub fn db_password_arg(help: &str) -> Arg {
         .help(help)
 }
 
+pub fn password_prompt_arg<'a>() -> Arg<'a, 'a> {
+    Arg::with_name("password-prompt")
+        .long("password-prompt")
+        .value_name("BACKEND")
+        .min_values(0)
+        .max_values(1)
+        .possible_values(&["tty", "pinentry", "environment"])
+        .help(PASSWORD_PROMPT_HELP)
+}
+
+// The whole numbers each numeric parameter in shared_app() will accept, so that a UI can offer a
+// number field with the right limits instead of a text box
+pub const INTEGER_PARAMETERS: &[(&str, u64, u64)] = &[
+    ("clandestine-key-lifetime", 1, std::u64::MAX),
+    ("clandestine-key-overlap", 1, std::u64::MAX),
+    (
+        "clandestine-port",
+        LOWEST_USABLE_INSECURE_PORT as u64,
+        HIGHEST_USABLE_PORT as u64,
+    ),
+    ("clock-skew-tolerance", 1, std::u32::MAX as u64),
+    ("data-cap", 1, std::u64::MAX),
+    ("dns-cache-size", 0, std::u32::MAX as u64),
+    ("dns-lookup-threads", 1, MAX_THREAD_COUNT as u64),
+    ("dns-port", 1, HIGHEST_USABLE_PORT as u64),
+    ("gas-price", 1, 99),
+    ("geo-db-interval", 1, std::u64::MAX),
+    (
+        "metrics-port",
+        LOWEST_USABLE_INSECURE_PORT as u64,
+        HIGHEST_USABLE_PORT as u64,
+    ),
+    (
+        "socks-port",
+        LOWEST_USABLE_INSECURE_PORT as u64,
+        HIGHEST_USABLE_PORT as u64,
+    ),
+    ("route-search-threads", 1, MAX_THREAD_COUNT as u64),
+    ("stream-idle-timeout", 0, std::u64::MAX),
+    ("update-check-interval", 1, std::u64::MAX),
+];
+
 pub fn earning_wallet_arg<F>(help: &str, validator: F) -> Arg
 where
     F: 'static,
@@ -167,6 +553,30 @@ where
         .help(help)
 }
 
+// For developers only: makes everything the Node would otherwise do at random depend on this
+// number instead, so that multinode tests and bug reproductions go the same way every time.
+// Give each Node in a network its own seed.
+pub fn deterministic_seed_arg<'a>() -> Arg<'a, 'a> {
+    Arg::with_name("deterministic-seed")
+        .long("deterministic-seed")
+        .value_name("DETERMINISTIC-SEED")
+        .takes_value(true)
+        .required(false)
+        .validator(common_validators::validate_deterministic_seed)
+        .hidden(true)
+}
+
+pub fn output_arg<'a>() -> Arg<'a, 'a> {
+    Arg::with_name("output")
+        .long("output")
+        .value_name("OUTPUT")
+        .takes_value(true)
+        .possible_values(&["human", "json"])
+        .case_insensitive(true)
+        .default_value("human")
+        .help(OUTPUT_HELP)
+}
+
 #[cfg(not(target_os = "windows"))]
 pub fn real_user_arg<'a>() -> Arg<'a, 'a> {
     Arg::with_name("real-user")
@@ -200,8 +610,166 @@ pub fn ui_port_arg(help: &str) -> Arg {
         .help(help)
 }
 
+pub fn ui_tls_arg<'a>() -> Arg<'a, 'a> {
+    Arg::with_name("ui-tls")
+        .long("ui-tls")
+        .value_name("UI-TLS")
+        .min_values(0)
+        .max_values(1)
+        .possible_values(&["off", "on"])
+        .case_insensitive(true)
+        .help(UI_TLS_HELP)
+}
+
+pub fn ui_tls_certificate_arg<'a>() -> Arg<'a, 'a> {
+    Arg::with_name("ui-tls-certificate")
+        .long("ui-tls-certificate")
+        .value_name("UI-TLS-CERTIFICATE")
+        .min_values(0)
+        .max_values(1)
+        .requires("ui-tls-key")
+        .help(UI_TLS_CERTIFICATE_HELP)
+}
+
+pub fn ui_tls_key_arg<'a>() -> Arg<'a, 'a> {
+    Arg::with_name("ui-tls-key")
+        .long("ui-tls-key")
+        .value_name("UI-TLS-KEY")
+        .min_values(0)
+        .max_values(1)
+        .requires("ui-tls-certificate")
+        .help(UI_TLS_KEY_HELP)
+}
+
+pub fn ui_interfaces_arg<'a>() -> Arg<'a, 'a> {
+    Arg::with_name("ui-interfaces")
+        .long("ui-interfaces")
+        .value_name("UI-INTERFACES")
+        .min_values(0)
+        .max_values(1)
+        .validator(common_validators::validate_ip_addresses)
+        .help(UI_INTERFACES_HELP)
+}
+
+pub fn ui_allowlist_arg<'a>() -> Arg<'a, 'a> {
+    Arg::with_name("ui-allowlist")
+        .long("ui-allowlist")
+        .value_name("UI-ALLOWLIST")
+        .min_values(0)
+        .max_values(1)
+        .validator(common_validators::validate_ip_networks)
+        .help(UI_ALLOWLIST_HELP)
+}
+
+pub fn ui_tls_client_ca_arg<'a>() -> Arg<'a, 'a> {
+    Arg::with_name("ui-tls-client-ca")
+        .long("ui-tls-client-ca")
+        .value_name("UI-TLS-CLIENT-CA")
+        .min_values(0)
+        .max_values(1)
+        .help(UI_TLS_CLIENT_CA_HELP)
+}
+
+pub fn ui_password_arg<'a>() -> Arg<'a, 'a> {
+    Arg::with_name("ui-password")
+        .long("ui-password")
+        .value_name("UI-PASSWORD")
+        .min_values(0)
+        .max_values(1)
+        .help(UI_PASSWORD_HELP)
+}
+
+pub fn ui_read_only_password_arg<'a>() -> Arg<'a, 'a> {
+    Arg::with_name("ui-read-only-password")
+        .long("ui-read-only-password")
+        .value_name("UI-READ-ONLY-PASSWORD")
+        .min_values(0)
+        .max_values(1)
+        .requires("ui-password")
+        .help(UI_READ_ONLY_PASSWORD_HELP)
+}
+
+pub fn ui_rest_port_arg<'a>() -> Arg<'a, 'a> {
+    Arg::with_name("ui-rest-port")
+        .long("ui-rest-port")
+        .value_name("UI-REST-PORT")
+        .min_values(0)
+        .max_values(1)
+        .validator(common_validators::validate_ui_port)
+        .help(UI_REST_PORT_HELP)
+}
+
+pub fn ui_grpc_port_arg(help: &str) -> Arg {
+    Arg::with_name("ui-grpc-port")
+        .long("ui-grpc-port")
+        .value_name("UI-GRPC-PORT")
+        .min_values(0)
+        .max_values(1)
+        .validator(common_validators::validate_ui_port)
+        .help(help)
+}
+
+pub fn health_port_arg<'a>() -> Arg<'a, 'a> {
+    Arg::with_name("health-port")
+        .long("health-port")
+        .value_name("HEALTH-PORT")
+        .min_values(0)
+        .max_values(1)
+        .validator(common_validators::validate_ui_port)
+        .help(HEALTH_PORT_HELP)
+}
+
+pub fn log_rotation_size_arg<'a>() -> Arg<'a, 'a> {
+    Arg::with_name("log-rotation-size")
+        .long("log-rotation-size")
+        .value_name("MEGABYTES")
+        .min_values(0)
+        .max_values(1)
+        .validator(common_validators::validate_log_rotation_limit)
+        .help(LOG_ROTATION_SIZE_HELP)
+}
+
+pub fn log_rotation_age_arg<'a>() -> Arg<'a, 'a> {
+    Arg::with_name("log-rotation-age")
+        .long("log-rotation-age")
+        .value_name("HOURS")
+        .min_values(0)
+        .max_values(1)
+        .validator(common_validators::validate_log_rotation_limit)
+        .help(LOG_ROTATION_AGE_HELP)
+}
+
+pub fn log_retention_arg<'a>() -> Arg<'a, 'a> {
+    Arg::with_name("log-retention")
+        .long("log-retention")
+        .value_name("COUNT")
+        .min_values(0)
+        .max_values(1)
+        .validator(common_validators::validate_log_retention)
+        .help(LOG_RETENTION_HELP)
+}
+
 pub fn shared_app(head: App<'static, 'static>) -> App<'static, 'static> {
     head.arg(
+        Arg::with_name("additional-clandestine-ports")
+            .long("additional-clandestine-ports")
+            .value_name("PORTS")
+            .min_values(0)
+            .max_values(1)
+            .validator(common_validators::validate_additional_clandestine_ports)
+            .help(&ADDITIONAL_CLANDESTINE_PORTS_HELP),
+    )
+    .arg(
+        Arg::with_name("bandwidth-benchmark")
+            .long("bandwidth-benchmark")
+            .value_name("BANDWIDTH-BENCHMARK")
+            .min_values(0)
+            .max_values(1)
+            .possible_values(&["off", "on"])
+            .case_insensitive(true)
+            .help(BANDWIDTH_BENCHMARK_HELP),
+    )
+    .arg(
         Arg::with_name("blockchain-service-url")
             .long("blockchain-service-url")
             .empty_values(false)
@@ -210,6 +778,24 @@ pub fn shared_app(head: App<'static, 'static>) -> App<'static, 'static> {
             .max_values(1)
             .help(BLOCKCHAIN_SERVICE_HELP),
     )
+    .arg(
+        Arg::with_name("clandestine-key-lifetime")
+            .long("clandestine-key-lifetime")
+            .value_name("DAYS")
+            .min_values(0)
+            .max_values(1)
+            .validator(common_validators::validate_clandestine_key_period)
+            .help(CLANDESTINE_KEY_LIFETIME_HELP),
+    )
+    .arg(
+        Arg::with_name("clandestine-key-overlap")
+            .long("clandestine-key-overlap")
+            .value_name("HOURS")
+            .min_values(0)
+            .max_values(1)
+            .validator(common_validators::validate_clandestine_key_period)
+            .help(CLANDESTINE_KEY_OVERLAP_HELP),
+    )
     .arg(
         Arg::with_name("clandestine-port")
             .long("clandestine-port")
@@ -219,6 +805,15 @@ pub fn shared_app(head: App<'static, 'static>) -> App<'static, 'static> {
             .validator(common_validators::validate_clandestine_port)
             .help(&CLANDESTINE_PORT_HELP),
     )
+    .arg(
+        Arg::with_name("clock-skew-tolerance")
+            .long("clock-skew-tolerance")
+            .value_name("SECONDS")
+            .min_values(0)
+            .max_values(1)
+            .validator(common_validators::validate_clock_skew_tolerance)
+            .help(CLOCK_SKEW_TOLERANCE_HELP),
+    )
     .arg(config_file_arg())
     .arg(
         Arg::with_name("consuming-private-key")
@@ -239,8 +834,63 @@ pub fn shared_app(head: App<'static, 'static>) -> App<'static, 'static> {
             .case_insensitive(true)
             .hidden(true),
     )
+    .arg(
+        Arg::with_name("cpu-affinity")
+            .long("cpu-affinity")
+            .value_name("CPU-LIST")
+            .min_values(0)
+            .max_values(1)
+            .validator(common_validators::validate_cpu_list)
+            .help(CPU_AFFINITY_HELP),
+    )
+    .arg(
+        Arg::with_name("data-cap")
+            .long("data-cap")
+            .value_name("GIGABYTES")
+            .min_values(0)
+            .max_values(1)
+            .validator(common_validators::validate_data_cap)
+            .help(DATA_CAP_HELP),
+    )
+    .arg(
+        Arg::with_name("data-cap-rates")
+            .long("data-cap-rates")
+            .value_name("RATES")
+            .min_values(0)
+            .max_values(1)
+            .validator(common_validators::validate_data_cap_rates)
+            .help(DATA_CAP_RATES_HELP),
+    )
     .arg(data_directory_arg())
     .arg(db_password_arg(DB_PASSWORD_HELP))
+    .arg(
+        Arg::with_name("descriptor-signature")
+            .long("descriptor-signature")
+            .value_name("SIGNATURE")
+            .min_values(0)
+            .max_values(1)
+            .validator(common_validators::validate_descriptor_signature)
+            .help(DESCRIPTOR_SIGNATURE_HELP),
+    )
+    .arg(deterministic_seed_arg())
+    .arg(
+        Arg::with_name("dns-cache-size")
+            .long("dns-cache-size")
+            .value_name("ENTRIES")
+            .min_values(0)
+            .max_values(1)
+            .validator(common_validators::validate_dns_cache_size)
+            .help(DNS_CACHE_SIZE_HELP),
+    )
+    .arg(
+        Arg::with_name("dns-lookup-threads")
+            .long("dns-lookup-threads")
+            .value_name("THREADS")
+            .min_values(0)
+            .max_values(1)
+            .validator(common_validators::validate_thread_count)
+            .help(DNS_LOOKUP_THREADS_HELP),
+    )
     .arg(
         Arg::with_name("dns-servers")
             .long("dns-servers")
@@ -250,11 +900,75 @@ pub fn shared_app(head: App<'static, 'static>) -> App<'static, 'static> {
             .validator(common_validators::validate_ip_addresses)
             .help(DNS_SERVERS_HELP),
     )
+    .arg(
+        Arg::with_name("dns-port")
+            .long("dns-port")
+            .value_name("DNS-PORT")
+            .min_values(0)
+            .max_values(1)
+            .validator(common_validators::validate_dns_port)
+            .help(DNS_PORT_HELP),
+    )
+    .arg(
+        Arg::with_name("dump-neighborhood")
+            .long("dump-neighborhood")
+            .value_name("DUMP-NEIGHBORHOOD")
+            .min_values(0)
+            .max_values(1)
+            .possible_values(&["off", "on"])
+            .case_insensitive(true)
+            .help(DUMP_NEIGHBORHOOD_HELP),
+    )
     .arg(earning_wallet_arg(
         EARNING_WALLET_HELP,
         common_validators::validate_ethereum_address,
     ))
+    .arg(
+        Arg::with_name("earning-wallet-change")
+            .long("earning-wallet-change")
+            .value_name("EARNING-WALLET-CHANGE")
+            .min_values(0)
+            .max_values(1)
+            .possible_values(&["off", "on"])
+            .case_insensitive(true)
+            .help(EARNING_WALLET_CHANGE_HELP),
+    )
+    .arg(
+        Arg::with_name("earning-wallet-change-signature")
+            .long("earning-wallet-change-signature")
+            .value_name("EARNING-WALLET-CHANGE-SIGNATURE")
+            .min_values(0)
+            .max_values(1)
+            .help(EARNING_WALLET_CHANGE_SIGNATURE_HELP),
+    )
     .arg(chain_arg())
+    .arg(
+        Arg::with_name("encrypted-sni")
+            .long("encrypted-sni")
+            .value_name("ENCRYPTED-SNI")
+            .min_values(0)
+            .max_values(1)
+            .validator(common_validators::validate_encrypted_sni)
+            .help(ENCRYPTED_SNI_HELP),
+    )
+    .arg(
+        Arg::with_name("exit-policy")
+            .long("exit-policy")
+            .value_name("EXIT-POLICY")
+            .min_values(0)
+            .max_values(1)
+            .validator(common_validators::validate_exit_policy)
+            .help(EXIT_POLICY_HELP),
+    )
+    .arg(
+        Arg::with_name("faucet-url")
+            .long("faucet-url")
+            .value_name("URL")
+            .min_values(0)
+            .max_values(1)
+            .validator(common_validators::validate_faucet_url)
+            .help(FAUCET_URL_HELP),
+    )
     .arg(
         Arg::with_name("fake-public-key")
             .long("fake-public-key")
@@ -272,6 +986,64 @@ pub fn shared_app(head: App<'static, 'static>) -> App<'static, 'static> {
             .validator(common_validators::validate_gas_price)
             .help(&GAS_PRICE_HELP),
     )
+    .arg(
+        Arg::with_name("geo-db-interval")
+            .long("geo-db-interval")
+            .value_name("HOURS")
+            .min_values(0)
+            .max_values(1)
+            .validator(common_validators::validate_geo_db_interval)
+            .help(GEO_DB_INTERVAL_HELP),
+    )
+    .arg(
+        Arg::with_name("geo-db-key")
+            .long("geo-db-key")
+            .value_name("PUBLIC-KEY")
+            .min_values(0)
+            .max_values(1)
+            .validator(common_validators::validate_geo_db_key)
+            .help(GEO_DB_KEY_HELP),
+    )
+    .arg(
+        Arg::with_name("geo-db-url")
+            .long("geo-db-url")
+            .value_name("URL")
+            .min_values(0)
+            .max_values(1)
+            .requires("geo-db-key")
+            .validator(common_validators::validate_geo_db_url)
+            .help(GEO_DB_URL_HELP),
+    )
+    .arg(
+        Arg::with_name("gossip-recording")
+            .long("gossip-recording")
+            .value_name("GOSSIP-RECORDING")
+            .min_values(0)
+            .max_values(1)
+            .possible_values(&["off", "on"])
+            .case_insensitive(true)
+            .help(GOSSIP_RECORDING_HELP),
+    )
+    .arg(
+        Arg::with_name("heed-attestations")
+            .long("heed-attestations")
+            .value_name("HEED-ATTESTATIONS")
+            .min_values(0)
+            .max_values(1)
+            .possible_values(&["off", "on"])
+            .case_insensitive(true)
+            .help(HEED_ATTESTATIONS_HELP),
+    )
+    .arg(
+        Arg::with_name("hop-timing")
+            .long("hop-timing")
+            .value_name("HOP-TIMING")
+            .min_values(0)
+            .max_values(1)
+            .possible_values(&["off", "on"])
+            .case_insensitive(true)
+            .help(HOP_TIMING_HELP),
+    )
     .arg(
         Arg::with_name("ip")
             .long("ip")
@@ -281,6 +1053,26 @@ pub fn shared_app(head: App<'static, 'static>) -> App<'static, 'static> {
             .validator(common_validators::validate_ip_address)
             .help(IP_ADDRESS_HELP),
     )
+    .arg(
+        Arg::with_name("local-discovery")
+            .long("local-discovery")
+            .value_name("LOCAL-DISCOVERY")
+            .min_values(0)
+            .max_values(1)
+            .possible_values(&["off", "on"])
+            .case_insensitive(true)
+            .help(LOCAL_DISCOVERY_HELP),
+    )
+    .arg(
+        Arg::with_name("local-proxy")
+            .long("local-proxy")
+            .value_name("LOCAL-PROXY")
+            .min_values(0)
+            .max_values(1)
+            .possible_values(&["off", "on"])
+            .case_insensitive(true)
+            .help(LOCAL_PROXY_HELP),
+    )
     .arg(
         Arg::with_name("log-level")
             .long("log-level")
@@ -291,6 +1083,44 @@ pub fn shared_app(head: App<'static, 'static>) -> App<'static, 'static> {
             .case_insensitive(true)
             .help(LOG_LEVEL_HELP),
     )
+    .arg(
+        Arg::with_name("log-redaction")
+            .long("log-redaction")
+            .value_name("LOG-REDACTION")
+            .min_values(0)
+            .max_values(1)
+            .possible_values(&["off", "on"])
+            .case_insensitive(true)
+            .help(LOG_REDACTION_HELP),
+    )
+    .arg(
+        Arg::with_name("masquerade-profiles")
+            .long("masquerade-profiles")
+            .value_name("MASQUERADE-PROFILES")
+            .min_values(0)
+            .max_values(1)
+            .validator(common_validators::validate_masquerade_profiles)
+            .help(MASQUERADE_PROFILES_HELP),
+    )
+    .arg(
+        Arg::with_name("metrics-port")
+            .long("metrics-port")
+            .value_name("METRICS-PORT")
+            .min_values(0)
+            .max_values(1)
+            .validator(common_validators::validate_ui_port)
+            .help(METRICS_PORT_HELP),
+    )
+    .arg(
+        Arg::with_name("multipath")
+            .long("multipath")
+            .value_name("MULTIPATH")
+            .min_values(0)
+            .max_values(1)
+            .possible_values(&["off", "stripe", "duplicate"])
+            .case_insensitive(true)
+            .help(MULTIPATH_HELP),
+    )
     .arg(
         Arg::with_name("neighborhood-mode")
             .long("neighborhood-mode")
@@ -308,21 +1138,195 @@ pub fn shared_app(head: App<'static, 'static>) -> App<'static, 'static> {
             .min_values(0)
             .help(NEIGHBORS_HELP),
     )
+    .arg(password_prompt_arg())
+    .arg(
+        Arg::with_name("payment-token")
+            .long("payment-token")
+            .value_name("SYMBOL:CONTRACT-ADDRESS:DECIMALS")
+            .min_values(0)
+            .max_values(1)
+            .validator(common_validators::validate_payment_token)
+            .help(PAYMENT_TOKEN_HELP),
+    )
+    .arg(
+        Arg::with_name("post-quantum")
+            .long("post-quantum")
+            .value_name("POST-QUANTUM")
+            .min_values(0)
+            .max_values(1)
+            .possible_values(&["off", "on"])
+            .case_insensitive(true)
+            .help(POST_QUANTUM_HELP),
+    )
+    .arg(
+        Arg::with_name("port-reselection")
+            .long("port-reselection")
+            .value_name("PORT-RESELECTION")
+            .min_values(0)
+            .max_values(1)
+            .possible_values(&["off", "on"])
+            .case_insensitive(true)
+            .help(PORT_RESELECTION_HELP),
+    )
+    .arg(
+        Arg::with_name("price-feed-url")
+            .long("price-feed-url")
+            .value_name("URL")
+            .min_values(0)
+            .max_values(1)
+            .validator(common_validators::validate_price_feed_url)
+            .help(PRICE_FEED_URL_HELP),
+    )
+    .arg(
+        Arg::with_name("protocol-sniffing")
+            .long("protocol-sniffing")
+            .value_name("PROTOCOL-SNIFFING")
+            .min_values(0)
+            .max_values(1)
+            .possible_values(&["off", "on"])
+            .case_insensitive(true)
+            .help(PROTOCOL_SNIFFING_HELP),
+    )
+    .arg(
+        Arg::with_name("proxied-domains")
+            .long("proxied-domains")
+            .value_name("PROXIED-DOMAINS")
+            .min_values(0)
+            .max_values(1)
+            .validator(common_validators::validate_proxied_domains)
+            .help(PROXIED_DOMAINS_HELP),
+    )
+    .arg(
+        Arg::with_name("publish-attestations")
+            .long("publish-attestations")
+            .value_name("PUBLISH-ATTESTATIONS")
+            .min_values(0)
+            .max_values(1)
+            .possible_values(&["off", "on"])
+            .case_insensitive(true)
+            .help(PUBLISH_ATTESTATIONS_HELP),
+    )
     .arg(real_user_arg())
-}
-
-pub mod common_validators {
-    use crate::constants::LOWEST_USABLE_INSECURE_PORT;
-    use regex::Regex;
-    use std::net::IpAddr;
-    use std::str::FromStr;
-    use tiny_hderive::bip44::DerivationPath;
-
-    pub fn validate_ip_address(address: String) -> Result<(), String> {
-        match IpAddr::from_str(&address) {
-            Ok(_) => Ok(()),
-            Err(_) => Err(address),
-        }
+    .arg(
+        Arg::with_name("reputation-lists")
+            .long("reputation-lists")
+            .value_name("REPUTATION-LISTS")
+            .min_values(0)
+            .max_values(1)
+            .validator(common_validators::validate_reputation_lists)
+            .help(REPUTATION_LISTS_HELP),
+    )
+    .arg(
+        Arg::with_name("route-search-threads")
+            .long("route-search-threads")
+            .value_name("THREADS")
+            .min_values(0)
+            .max_values(1)
+            .validator(common_validators::validate_thread_count)
+            .help(ROUTE_SEARCH_THREADS_HELP),
+    )
+    .arg(
+        Arg::with_name("sandbox")
+            .long("sandbox")
+            .value_name("SANDBOX")
+            .min_values(0)
+            .max_values(1)
+            .possible_values(&["off", "on"])
+            .case_insensitive(true)
+            .help(SANDBOX_HELP),
+    )
+    .arg(
+        Arg::with_name("secure-dns-servers")
+            .long("secure-dns-servers")
+            .value_name("SECURE-DNS-SERVERS")
+            .min_values(0)
+            .max_values(1)
+            .validator(common_validators::validate_secure_dns_servers)
+            .help(SECURE_DNS_SERVERS_HELP),
+    )
+    .arg(
+        Arg::with_name("socks-credentials")
+            .long("socks-credentials")
+            .value_name("USERNAME:PASSWORD")
+            .min_values(0)
+            .max_values(1)
+            .validator(common_validators::validate_socks_credentials)
+            .help(SOCKS_CREDENTIALS_HELP),
+    )
+    .arg(
+        Arg::with_name("socks-port")
+            .long("socks-port")
+            .value_name("SOCKS-PORT")
+            .min_values(0)
+            .max_values(1)
+            .validator(common_validators::validate_socks_port)
+            .help(&SOCKS_PORT_HELP),
+    )
+    .arg(
+        Arg::with_name("stream-idle-timeout")
+            .long("stream-idle-timeout")
+            .value_name("STREAM-IDLE-TIMEOUT")
+            .min_values(0)
+            .max_values(1)
+            .validator(common_validators::validate_stream_idle_timeout)
+            .help(STREAM_IDLE_TIMEOUT_HELP),
+    )
+    .arg(
+        Arg::with_name("time-servers")
+            .long("time-servers")
+            .value_name("TIME-SERVERS")
+            .min_values(0)
+            .max_values(1)
+            .validator(common_validators::validate_time_servers)
+            .help(TIME_SERVERS_HELP),
+    )
+    .arg(
+        Arg::with_name("update-check-interval")
+            .long("update-check-interval")
+            .value_name("HOURS")
+            .min_values(0)
+            .max_values(1)
+            .validator(common_validators::validate_update_check_interval)
+            .help(UPDATE_CHECK_INTERVAL_HELP),
+    )
+    .arg(
+        Arg::with_name("update-check-key")
+            .long("update-check-key")
+            .value_name("PUBLIC-KEY")
+            .min_values(0)
+            .max_values(1)
+            .validator(common_validators::validate_update_check_key)
+            .help(UPDATE_CHECK_KEY_HELP),
+    )
+    .arg(
+        Arg::with_name("update-check-url")
+            .long("update-check-url")
+            .value_name("URL")
+            .min_values(0)
+            .max_values(1)
+            .requires("update-check-key")
+            .validator(common_validators::validate_update_check_url)
+            .help(UPDATE_CHECK_URL_HELP),
+    )
+}
+
+pub mod common_validators {
+    use crate::constants::{LOWEST_USABLE_INSECURE_PORT, MAX_THREAD_COUNT};
+    use crate::data_cap::DataCapRate;
+    use crate::exit_policy::ExitPolicy;
+    use crate::ip_network::IpNetwork;
+    use crate::secure_dns::SecureDnsServer;
+    use crate::utils::parse_port_list;
+    use regex::Regex;
+    use std::net::IpAddr;
+    use std::str::FromStr;
+    use tiny_hderive::bip44::DerivationPath;
+
+    pub fn validate_ip_address(address: String) -> Result<(), String> {
+        match IpAddr::from_str(&address) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(address),
+        }
     }
 
     pub fn validate_ip_addresses(addresses: String) -> Result<(), String> {
@@ -342,6 +1346,19 @@ pub mod common_validators {
         }
     }
 
+    pub fn validate_ip_networks(networks: String) -> Result<(), String> {
+        let errors = networks
+            .split(',')
+            .flat_map(|network| IpNetwork::from_str(network).err())
+            .collect::<Vec<String>>()
+            .join(";");
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
     pub fn validate_clandestine_port(clandestine_port: String) -> Result<(), String> {
         match clandestine_port.parse::<u16>() {
             Ok(clandestine_port) if clandestine_port >= LOWEST_USABLE_INSECURE_PORT => Ok(()),
@@ -349,6 +1366,273 @@ pub mod common_validators {
         }
     }
 
+    pub fn validate_additional_clandestine_ports(ports: String) -> Result<(), String> {
+        match parse_port_list(&ports) {
+            Ok(list) if list.iter().all(|port| *port >= LOWEST_USABLE_INSECURE_PORT) => Ok(()),
+            _ => Err(ports),
+        }
+    }
+
+    pub fn validate_dns_cache_size(entries: String) -> Result<(), String> {
+        match entries.parse::<u32>() {
+            Ok(_) => Ok(()),
+            Err(_) => Err(entries),
+        }
+    }
+
+    pub fn validate_thread_count(threads: String) -> Result<(), String> {
+        match threads.parse::<usize>() {
+            Ok(count) if count > 0 && count <= MAX_THREAD_COUNT => Ok(()),
+            _ => Err(threads),
+        }
+    }
+
+    pub fn validate_dns_port(port: String) -> Result<(), String> {
+        match port.parse::<u16>() {
+            Ok(port_number) if port_number > 0 => Ok(()),
+            _ => Err(port),
+        }
+    }
+
+    // refuse, prompt, or a host name
+    pub fn validate_encrypted_sni(fallback: String) -> Result<(), String> {
+        if Regex::new("^[A-Za-z0-9.-]+$")
+            .expect("Failed to compile regular expression")
+            .is_match(&fallback)
+        {
+            Ok(())
+        } else {
+            Err(fallback)
+        }
+    }
+
+    pub fn validate_exit_policy(policy: String) -> Result<(), String> {
+        ExitPolicy::from_str(&policy).map(|_| ())
+    }
+
+    pub fn validate_masquerade_profiles(profiles: String) -> Result<(), String> {
+        let errors = profiles
+            .split(',')
+            .filter(|profile| !["websocket", "json"].contains(profile))
+            .collect::<Vec<&str>>()
+            .join(";");
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    pub fn validate_proxied_domains(domains: String) -> Result<(), String> {
+        let regex = Regex::new("^[A-Za-z0-9-]+(\\.[A-Za-z0-9-]+)*\\.?$")
+            .expect("Failed to compile regular expression");
+        let errors = domains
+            .split(',')
+            .filter(|domain| !regex.is_match(domain))
+            .collect::<Vec<&str>>()
+            .join(";");
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    pub fn validate_reputation_lists(lists: String) -> Result<(), String> {
+        validate_proxied_domains(lists)
+    }
+
+    pub fn validate_secure_dns_servers(servers: String) -> Result<(), String> {
+        let errors = servers
+            .split(',')
+            .flat_map(|server| SecureDnsServer::from_str(server).err())
+            .collect::<Vec<String>>()
+            .join(";");
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    pub fn validate_socks_port(port: String) -> Result<(), String> {
+        match port.parse::<u16>() {
+            Ok(port_number) if port_number >= LOWEST_USABLE_INSECURE_PORT => Ok(()),
+            _ => Err(port),
+        }
+    }
+
+    pub fn validate_socks_credentials(credentials: String) -> Result<(), String> {
+        match credentials.find(':') {
+            Some(idx) if idx > 0 && idx <= 255 && credentials.len() - idx - 1 <= 255 => Ok(()),
+            _ => Err(credentials),
+        }
+    }
+
+    pub fn validate_stream_idle_timeout(seconds: String) -> Result<(), String> {
+        match seconds.parse::<u64>() {
+            Ok(_) => Ok(()),
+            Err(_) => Err(seconds),
+        }
+    }
+
+    pub fn validate_time_servers(servers: String) -> Result<(), String> {
+        if servers == "off" {
+            return Ok(());
+        }
+        let errors = servers
+            .split(',')
+            .filter(|server| {
+                let mut pieces = server.splitn(2, ':');
+                let host = pieces.next().unwrap_or("");
+                let port_ok = match pieces.next() {
+                    Some(port) => match port.parse::<u16>() {
+                        Ok(number) => number > 0,
+                        Err(_) => false,
+                    },
+                    None => true,
+                };
+                host.is_empty() || host.contains(char::is_whitespace) || !port_ok
+            })
+            .collect::<Vec<&str>>()
+            .join(";");
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    pub fn validate_clock_skew_tolerance(seconds: String) -> Result<(), String> {
+        match seconds.parse::<u32>() {
+            Ok(value) if value > 0 => Ok(()),
+            _ => Err(seconds),
+        }
+    }
+
+    pub fn validate_cpu_list(cpus: String) -> Result<(), String> {
+        let valid = cpus.split(',').all(|item| {
+            let bounds = item
+                .splitn(2, '-')
+                .map(|bound| bound.trim().parse::<usize>())
+                .collect::<Vec<Result<usize, _>>>();
+            match bounds.as_slice() {
+                [Ok(_)] => true,
+                [Ok(low), Ok(high)] => low <= high,
+                _ => false,
+            }
+        });
+        if valid {
+            Ok(())
+        } else {
+            Err(cpus)
+        }
+    }
+
+    pub fn validate_data_cap(gigabytes: String) -> Result<(), String> {
+        match gigabytes.parse::<u64>() {
+            Ok(gigabytes) if gigabytes > 0 => Ok(()),
+            _ => Err(gigabytes),
+        }
+    }
+
+    pub fn validate_data_cap_rates(rates: String) -> Result<(), String> {
+        let errors = rates
+            .split(',')
+            .flat_map(|rate| DataCapRate::from_str(rate).err())
+            .collect::<Vec<String>>()
+            .join(";");
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    pub fn validate_deterministic_seed(seed: String) -> Result<(), String> {
+        match seed.parse::<u64>() {
+            Ok(_) => Ok(()),
+            Err(_) => Err(seed),
+        }
+    }
+
+    pub fn validate_restart_max_retries(retries: String) -> Result<(), String> {
+        match retries.parse::<u32>() {
+            Ok(_) => Ok(()),
+            Err(_) => Err(retries),
+        }
+    }
+
+    pub fn validate_restart_backoff(seconds: String) -> Result<(), String> {
+        match seconds.parse::<u64>() {
+            Ok(value) if value > 0 => Ok(()),
+            _ => Err(seconds),
+        }
+    }
+
+    pub fn validate_log_rotation_limit(limit: String) -> Result<(), String> {
+        match limit.parse::<u64>() {
+            Ok(_) => Ok(()),
+            Err(_) => Err(limit),
+        }
+    }
+
+    pub fn validate_log_retention(count: String) -> Result<(), String> {
+        match count.parse::<u64>() {
+            Ok(value) if value > 0 => Ok(()),
+            _ => Err(count),
+        }
+    }
+
+    pub fn validate_update_check_interval(hours: String) -> Result<(), String> {
+        match hours.parse::<u64>() {
+            Ok(value) if value > 0 => Ok(()),
+            _ => Err(hours),
+        }
+    }
+
+    pub fn validate_clandestine_key_period(period: String) -> Result<(), String> {
+        match period.parse::<u64>() {
+            Ok(value) if value > 0 => Ok(()),
+            _ => Err(period),
+        }
+    }
+
+    pub fn validate_update_check_key(key: String) -> Result<(), String> {
+        validate_private_key(key)
+    }
+
+    pub fn validate_geo_db_interval(hours: String) -> Result<(), String> {
+        validate_update_check_interval(hours)
+    }
+
+    pub fn validate_geo_db_key(key: String) -> Result<(), String> {
+        validate_private_key(key)
+    }
+
+    pub fn validate_faucet_url(url: String) -> Result<(), String> {
+        validate_update_check_url(url)
+    }
+
+    pub fn validate_price_feed_url(url: String) -> Result<(), String> {
+        validate_update_check_url(url)
+    }
+
+    pub fn validate_geo_db_url(url: String) -> Result<(), String> {
+        validate_update_check_url(url)
+    }
+
+    pub fn validate_update_check_url(url: String) -> Result<(), String> {
+        if Regex::new("^https://[^/:?#\\s]+(:[0-9]{1,5})?(/\\S*)?$")
+            .expect("Failed to compile regular expression")
+            .is_match(&url)
+        {
+            Ok(())
+        } else {
+            Err(url)
+        }
+    }
+
     pub fn validate_private_key(key: String) -> Result<(), String> {
         if Regex::new("^[0-9a-fA-F]{64}$")
             .expect("Failed to compile regular expression")
@@ -371,6 +1655,17 @@ pub mod common_validators {
         validate_ethereum_address(value.clone()).or_else(|_| validate_derivation_path(value))
     }
 
+    pub fn validate_descriptor_signature(signature: String) -> Result<(), String> {
+        if Regex::new("^0x[0-9a-fA-F]{130}$")
+            .expect("Failed to compile regular expression")
+            .is_match(&signature)
+        {
+            Ok(())
+        } else {
+            Err(signature)
+        }
+    }
+
     pub fn validate_ethereum_address(address: String) -> Result<(), String> {
         if Regex::new("^0x[0-9a-fA-F]{40}$")
             .expect("Failed to compile regular expression")
@@ -382,6 +1677,17 @@ pub mod common_validators {
         }
     }
 
+    // <symbol>:<contract address>:<decimals>, with no more decimals than a uint256 can use
+    pub fn validate_payment_token(token: String) -> Result<(), String> {
+        let captures_opt = Regex::new("^[A-Za-z0-9]{1,11}:0x[0-9a-fA-F]{40}:([0-9]{1,2})$")
+            .expect("Failed to compile regular expression")
+            .captures(&token);
+        match captures_opt.map(|captures| captures[1].parse::<u8>()) {
+            Some(Ok(decimals)) if decimals <= 36 => Ok(()),
+            _ => Err(token),
+        }
+    }
+
     pub fn validate_derivation_path(path: String) -> Result<(), String> {
         let possible_path = path.parse::<DerivationPath>();
 
@@ -429,16 +1735,41 @@ pub mod common_validators {
     }
 }
 
+// Tells a UI what kind of trouble a parameter is in without making it parse the reason
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq)]
+pub enum ParamErrorCode {
+    // The value itself is unacceptable
+    Invalid,
+    // There's no value, and there has to be one
+    Required,
+    // The value is fine by itself, but not together with another parameter's
+    Conflict,
+    // The value is acceptable, but the Node couldn't do what it asked: the database already has a
+    // seed, say, or a key couldn't be derived
+    Failed,
+}
+
+// What the Node exits with when it can't be configured: 1 when the parameters are wrong, 2 when
+// they're right but couldn't be carried out. Panics, and whatever exit code they bring, are for bugs.
+pub const CONFIGURATION_ERROR_EXIT_CODE: i32 = 1;
+pub const CONFIGURATION_FAILURE_EXIT_CODE: i32 = 2;
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct ParamError {
     pub parameter: String,
+    pub code: ParamErrorCode,
     pub reason: String,
 }
 
 impl ParamError {
     pub fn new(parameter: &str, reason: &str) -> Self {
+        Self::with_code(parameter, ParamErrorCode::Invalid, reason)
+    }
+
+    pub fn with_code(parameter: &str, code: ParamErrorCode, reason: &str) -> Self {
         Self {
             parameter: parameter.to_string(),
+            code,
             reason: reason.to_string(),
         }
     }
@@ -460,6 +1791,26 @@ impl ConfiguratorError {
         }
     }
 
+    // One error for each of the parameters that can't be used together, so that each can be flagged
+    pub fn conflict(parameters: &[&str], reason: &str) -> Self {
+        ConfiguratorError {
+            param_errors: parameters
+                .iter()
+                .map(|parameter| ParamError::with_code(parameter, ParamErrorCode::Conflict, reason))
+                .collect(),
+        }
+    }
+
+    pub fn failed(parameter: &str, reason: &str) -> Self {
+        ConfiguratorError {
+            param_errors: vec![ParamError::with_code(
+                parameter,
+                ParamErrorCode::Failed,
+                reason,
+            )],
+        }
+    }
+
     pub fn another_required(mut self, parameter: &str, reason: &str) -> Self {
         self.param_errors.push(ParamError::new(parameter, reason));
         self
@@ -468,42 +1819,172 @@ impl ConfiguratorError {
     pub fn extend(&mut self, extension: Self) {
         self.param_errors.extend(extension.param_errors);
     }
+
+    pub fn exit_code(&self) -> i32 {
+        if self
+            .param_errors
+            .iter()
+            .any(|param_error| param_error.code == ParamErrorCode::Failed)
+        {
+            CONFIGURATION_FAILURE_EXIT_CODE
+        } else {
+            CONFIGURATION_ERROR_EXIT_CODE
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::constants::MAX_THREAD_COUNT;
     use crate::shared_schema::common_validators;
+    use crate::shared_schema::{
+        shared_app, ConfiguratorError, ParamError, ParamErrorCode, CONFIGURATION_ERROR_EXIT_CODE,
+        CONFIGURATION_FAILURE_EXIT_CODE, INTEGER_PARAMETERS,
+    };
+    use clap::App;
 
     #[test]
-    fn validate_private_key_requires_a_key_that_is_64_characters_long() {
-        let result = common_validators::validate_private_key(String::from("42"));
+    fn integer_parameters_match_what_their_validators_accept() {
+        let schema = shared_app(App::new("test"));
 
-        assert_eq!(Err("42".to_string()), result);
+        INTEGER_PARAMETERS
+            .iter()
+            .for_each(|(name, minimum, maximum)| {
+                let opt = schema
+                    .p
+                    .opts
+                    .iter()
+                    .find(|opt| opt.b.name == *name)
+                    .unwrap_or_else(|| panic!("{} is not in the shared schema", name));
+                let validator = opt.v.validator.as_ref().unwrap();
+                assert_eq!(validator(minimum.to_string()), Ok(()), "{}", name);
+                assert_eq!(validator(maximum.to_string()), Ok(()), "{}", name);
+                if *minimum > 0 {
+                    assert!(validator((minimum - 1).to_string()).is_err(), "{}", name);
+                }
+                if *maximum < std::u64::MAX {
+                    assert!(validator((maximum + 1).to_string()).is_err(), "{}", name);
+                }
+            });
     }
 
     #[test]
-    fn validate_private_key_must_contain_only_hex_characters() {
-        let result = common_validators::validate_private_key(String::from(
-            "cc46befe8d169b89db447bd725fc2368b12542113555302598430cinvalidhex",
-        ));
+    fn conflict_flags_every_parameter_involved() {
+        let result = ConfiguratorError::conflict(&["neighborhood-mode", "neighbors"], "No way");
 
         assert_eq!(
-            Err("cc46befe8d169b89db447bd725fc2368b12542113555302598430cinvalidhex".to_string()),
-            result
+            result,
+            ConfiguratorError::new(vec![
+                ParamError::with_code("neighborhood-mode", ParamErrorCode::Conflict, "No way"),
+                ParamError::with_code("neighbors", ParamErrorCode::Conflict, "No way"),
+            ])
         );
+        assert_eq!(ParamError::new("ip", "Bad").code, ParamErrorCode::Invalid);
     }
 
     #[test]
-    fn validate_private_key_handles_happy_path() {
-        let result = common_validators::validate_private_key(String::from(
-            "cc46befe8d169b89db447bd725fc2368b12542113555302598430cb5d5c74ea9",
-        ));
-
-        assert_eq!(Ok(()), result);
+    fn a_failure_anywhere_makes_a_configurator_error_exit_with_the_failure_code() {
+        let wrong = ConfiguratorError::required("db-password", "Must be supplied")
+            .another_required("ip", "Bad");
+        let mut failed = wrong.clone();
+        failed.extend(ConfiguratorError::failed("seed", "Already there"));
+
+        assert_eq!(wrong.exit_code(), CONFIGURATION_ERROR_EXIT_CODE);
+        assert_eq!(failed.exit_code(), CONFIGURATION_FAILURE_EXIT_CODE);
+        assert_eq!(
+            failed.param_errors[2],
+            ParamError::with_code("seed", ParamErrorCode::Failed, "Already there")
+        );
     }
 
     #[test]
-    fn validate_ip_address_given_invalid_input() {
+    fn validate_update_check_url_requires_https() {
+        assert_eq!(
+            common_validators::validate_update_check_url(
+                "https://releases.example.com/node/manifest.json".to_string()
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            common_validators::validate_update_check_url(
+                "https://releases.example.com:8443".to_string()
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            common_validators::validate_update_check_url(
+                "http://releases.example.com/manifest.json".to_string()
+            ),
+            Err("http://releases.example.com/manifest.json".to_string())
+        );
+        assert_eq!(
+            common_validators::validate_update_check_url("https://".to_string()),
+            Err("https://".to_string())
+        );
+    }
+
+    #[test]
+    fn validate_update_check_interval_requires_positive_hours() {
+        assert_eq!(
+            common_validators::validate_update_check_interval("12".to_string()),
+            Ok(())
+        );
+        assert_eq!(
+            common_validators::validate_update_check_interval("0".to_string()),
+            Err("0".to_string())
+        );
+    }
+
+    #[test]
+    fn validate_private_key_requires_a_key_that_is_64_characters_long() {
+        let result = common_validators::validate_private_key(String::from("42"));
+
+        assert_eq!(Err("42".to_string()), result);
+    }
+
+    #[test]
+    fn validate_private_key_must_contain_only_hex_characters() {
+        let result = common_validators::validate_private_key(String::from(
+            "cc46befe8d169b89db447bd725fc2368b12542113555302598430cinvalidhex",
+        ));
+
+        assert_eq!(
+            Err("cc46befe8d169b89db447bd725fc2368b12542113555302598430cinvalidhex".to_string()),
+            result
+        );
+    }
+
+    #[test]
+    fn validate_private_key_handles_happy_path() {
+        let result = common_validators::validate_private_key(String::from(
+            "cc46befe8d169b89db447bd725fc2368b12542113555302598430cb5d5c74ea9",
+        ));
+
+        assert_eq!(Ok(()), result);
+    }
+
+    #[test]
+    fn validate_descriptor_signature_wants_65_hexadecimal_bytes() {
+        let good = format!("0x{}1c", "aB".repeat(64));
+        let short = format!("0x{}", "ab".repeat(64));
+        let unprefixed = format!("{}1c", "ab".repeat(64));
+
+        assert_eq!(
+            common_validators::validate_descriptor_signature(good),
+            Ok(())
+        );
+        assert_eq!(
+            common_validators::validate_descriptor_signature(short.clone()),
+            Err(short)
+        );
+        assert_eq!(
+            common_validators::validate_descriptor_signature(unprefixed.clone()),
+            Err(unprefixed)
+        );
+    }
+
+    #[test]
+    fn validate_ip_address_given_invalid_input() {
         assert_eq!(
             Err(String::from("not-a-valid-IP")),
             common_validators::validate_ip_address(String::from("not-a-valid-IP")),
@@ -539,6 +2020,74 @@ mod tests {
         assert_eq!(Err(String::from("65536")), result);
     }
 
+    #[test]
+    fn validate_restart_max_retries_accepts_zero_and_refuses_negatives() {
+        assert_eq!(
+            common_validators::validate_restart_max_retries("0".to_string()),
+            Ok(())
+        );
+        assert_eq!(
+            common_validators::validate_restart_max_retries("-1".to_string()),
+            Err("-1".to_string())
+        );
+    }
+
+    #[test]
+    fn validate_log_rotation_limit_accepts_zero_and_refuses_negatives() {
+        assert_eq!(
+            common_validators::validate_log_rotation_limit("0".to_string()),
+            Ok(())
+        );
+        assert_eq!(
+            common_validators::validate_log_rotation_limit("-1".to_string()),
+            Err("-1".to_string())
+        );
+    }
+
+    #[test]
+    fn validate_log_retention_refuses_zero() {
+        assert_eq!(
+            common_validators::validate_log_retention("7".to_string()),
+            Ok(())
+        );
+        assert_eq!(
+            common_validators::validate_log_retention("0".to_string()),
+            Err("0".to_string())
+        );
+    }
+
+    #[test]
+    fn validate_clandestine_key_period_refuses_zero() {
+        assert_eq!(
+            common_validators::validate_clandestine_key_period("30".to_string()),
+            Ok(())
+        );
+        assert_eq!(
+            common_validators::validate_clandestine_key_period("0".to_string()),
+            Err("0".to_string())
+        );
+        assert_eq!(
+            common_validators::validate_clandestine_key_period("booga".to_string()),
+            Err("booga".to_string())
+        );
+    }
+
+    #[test]
+    fn validate_restart_backoff_refuses_zero() {
+        assert_eq!(
+            common_validators::validate_restart_backoff("5".to_string()),
+            Ok(())
+        );
+        assert_eq!(
+            common_validators::validate_restart_backoff("0".to_string()),
+            Err("0".to_string())
+        );
+        assert_eq!(
+            common_validators::validate_restart_backoff("booga".to_string()),
+            Err("booga".to_string())
+        );
+    }
+
     #[test]
     fn validate_ui_port_works() {
         let result = common_validators::validate_ui_port(String::from("5335"));
@@ -575,6 +2124,377 @@ mod tests {
         assert_eq!(Ok(()), result);
     }
 
+    #[test]
+    fn validate_additional_clandestine_ports_accepts_ports_and_ranges() {
+        let result = common_validators::validate_additional_clandestine_ports(String::from(
+            "1025,8443-8445",
+        ));
+
+        assert_eq!(Ok(()), result);
+    }
+
+    #[test]
+    fn validate_additional_clandestine_ports_rejects_port_number_too_low() {
+        let result =
+            common_validators::validate_additional_clandestine_ports(String::from("443,8443"));
+
+        assert_eq!(Err(String::from("443,8443")), result);
+    }
+
+    #[test]
+    fn validate_additional_clandestine_ports_rejects_badly_formatted_list() {
+        let result =
+            common_validators::validate_additional_clandestine_ports(String::from("8443-booga"));
+
+        assert_eq!(Err(String::from("8443-booga")), result);
+    }
+
+    #[test]
+    fn validate_additional_clandestine_ports_rejects_too_many_ports() {
+        let result =
+            common_validators::validate_additional_clandestine_ports(String::from("8000-9000"));
+
+        assert_eq!(Err(String::from("8000-9000")), result);
+    }
+
+    #[test]
+    fn validate_cpu_list_accepts_cores_and_ranges_of_cores() {
+        assert_eq!(
+            common_validators::validate_cpu_list(String::from("0")),
+            Ok(())
+        );
+        assert_eq!(
+            common_validators::validate_cpu_list(String::from("0,2-5,7")),
+            Ok(())
+        );
+        assert_eq!(
+            common_validators::validate_cpu_list(String::from("5-2")),
+            Err(String::from("5-2"))
+        );
+        assert_eq!(
+            common_validators::validate_cpu_list(String::from("0,,1")),
+            Err(String::from("0,,1"))
+        );
+        assert_eq!(
+            common_validators::validate_cpu_list(String::from("booga")),
+            Err(String::from("booga"))
+        );
+    }
+
+    #[test]
+    fn validate_data_cap_accepts_only_positive_gigabytes() {
+        assert_eq!(
+            common_validators::validate_data_cap(String::from("50")),
+            Ok(())
+        );
+        assert_eq!(
+            common_validators::validate_data_cap(String::from("0")),
+            Err(String::from("0"))
+        );
+        assert_eq!(
+            common_validators::validate_data_cap(String::from("booga")),
+            Err(String::from("booga"))
+        );
+    }
+
+    #[test]
+    fn validate_data_cap_rates_accepts_good_rates_and_complains_about_bad_ones() {
+        assert_eq!(
+            common_validators::validate_data_cap_rates(String::from("0-6:0,18-23:200")),
+            Ok(())
+        );
+        assert_eq!(
+            common_validators::validate_data_cap_rates(String::from("0-6:0,booga")),
+            Err(String::from(
+                "'booga' should look like <FROM-HOUR>-<TO-HOUR>:<PERCENT>, with hours from 0 to 24"
+            ))
+        );
+    }
+
+    #[test]
+    fn validate_deterministic_seed_accepts_only_unsigned_integers() {
+        assert_eq!(
+            common_validators::validate_deterministic_seed(String::from("1234")),
+            Ok(())
+        );
+        assert_eq!(
+            common_validators::validate_deterministic_seed(String::from("-1")),
+            Err(String::from("-1"))
+        );
+        assert_eq!(
+            common_validators::validate_deterministic_seed(String::from("booga")),
+            Err(String::from("booga"))
+        );
+    }
+
+    #[test]
+    fn validate_stream_idle_timeout_accepts_seconds() {
+        let result = common_validators::validate_stream_idle_timeout(String::from("300"));
+
+        assert_eq!(result, Ok(()));
+    }
+
+    #[test]
+    fn validate_stream_idle_timeout_rejects_non_numbers() {
+        let result = common_validators::validate_stream_idle_timeout(String::from("5m"));
+
+        assert_eq!(result, Err(String::from("5m")));
+    }
+
+    #[test]
+    fn validate_encrypted_sni_accepts_keywords_and_host_names() {
+        vec!["refuse", "Prompt", "crypto.cloudflare.com"]
+            .into_iter()
+            .for_each(|fallback| {
+                assert_eq!(
+                    common_validators::validate_encrypted_sni(fallback.to_string()),
+                    Ok(())
+                )
+            });
+    }
+
+    #[test]
+    fn validate_encrypted_sni_rejects_what_cant_be_a_host_name() {
+        let result = common_validators::validate_encrypted_sni(String::from("booga:443"));
+
+        assert_eq!(result, Err(String::from("booga:443")));
+    }
+
+    #[test]
+    fn validate_exit_policy_accepts_good_policy() {
+        let result = common_validators::validate_exit_policy(String::from(
+            "port:25,port:6881-6889,host:example.com,category:tor",
+        ));
+
+        assert_eq!(result, Ok(()));
+    }
+
+    #[test]
+    fn validate_exit_policy_rejects_bad_policy() {
+        let result = common_validators::validate_exit_policy(String::from("port:25,booga"));
+
+        assert_eq!(
+            result,
+            Err(String::from(
+                "Exit policy entry 'booga' must be port:, host:, or category:"
+            ))
+        );
+    }
+
+    #[test]
+    fn validate_time_servers_names_the_bad_servers() {
+        let good = common_validators::validate_time_servers(String::from(
+            "pool.ntp.org,time.cloudflare.com:123,192.168.0.1:1123",
+        ));
+        let off = common_validators::validate_time_servers(String::from("off"));
+        let bad = common_validators::validate_time_servers(String::from(
+            "pool.ntp.org,:123,time.cloudflare.com:0,ntp:booga",
+        ));
+
+        assert_eq!(good, Ok(()));
+        assert_eq!(off, Ok(()));
+        assert_eq!(
+            bad,
+            Err(String::from(":123;time.cloudflare.com:0;ntp:booga"))
+        );
+    }
+
+    #[test]
+    fn validate_masquerade_profiles_names_the_unknown_profiles() {
+        let good = common_validators::validate_masquerade_profiles(String::from("websocket,json"));
+        let bad = common_validators::validate_masquerade_profiles(String::from(
+            "websocket,booga,WebSocket",
+        ));
+
+        assert_eq!(good, Ok(()));
+        assert_eq!(bad, Err(String::from("booga;WebSocket")));
+    }
+
+    #[test]
+    fn validate_ip_networks_accepts_networks_and_addresses() {
+        let result =
+            common_validators::validate_ip_networks(String::from("192.168.0.0/24,203.0.113.7,::1"));
+
+        assert_eq!(result, Ok(()));
+    }
+
+    #[test]
+    fn validate_ip_networks_rejects_bad_networks() {
+        let result =
+            common_validators::validate_ip_networks(String::from("10.0.0.0/8,booga,10.0.0.0/40"));
+
+        assert_eq!(
+            result,
+            Err(String::from(
+                "'booga' is not a valid CIDR network;'10.0.0.0/40' has an invalid network prefix"
+            ))
+        );
+    }
+
+    #[test]
+    fn validate_secure_dns_servers_accepts_https_and_tls_servers() {
+        let result = common_validators::validate_secure_dns_servers(String::from(
+            "https://cloudflare-dns.com/dns-query,tls://[2606:4700:4700::1111]:853",
+        ));
+
+        assert_eq!(result, Ok(()));
+    }
+
+    #[test]
+    fn validate_secure_dns_servers_rejects_bad_servers() {
+        let result = common_validators::validate_secure_dns_servers(String::from(
+            "tls://1.1.1.1,1.0.0.1,tls://1.1.1.1:booga",
+        ));
+
+        assert_eq!(
+            result,
+            Err(String::from(
+                "Secure DNS server '1.0.0.1' must start with https:// or tls://;\
+                 Secure DNS server 'tls://1.1.1.1:booga' has an invalid port"
+            ))
+        );
+    }
+
+    #[test]
+    fn validate_socks_port_rejects_port_number_too_low() {
+        let result = common_validators::validate_socks_port(String::from("1024"));
+
+        assert_eq!(Err(String::from("1024")), result);
+    }
+
+    #[test]
+    fn validate_socks_port_accepts_port() {
+        let result = common_validators::validate_socks_port(String::from("1080"));
+
+        assert_eq!(Ok(()), result);
+    }
+
+    #[test]
+    fn validate_dns_cache_size_accepts_zero_and_rejects_non_numbers() {
+        assert_eq!(
+            common_validators::validate_dns_cache_size(String::from("0")),
+            Ok(())
+        );
+        assert_eq!(
+            common_validators::validate_dns_cache_size(String::from("5000")),
+            Ok(())
+        );
+        assert_eq!(
+            common_validators::validate_dns_cache_size(String::from("-1")),
+            Err(String::from("-1"))
+        );
+        assert_eq!(
+            common_validators::validate_dns_cache_size(String::from("lots")),
+            Err(String::from("lots"))
+        );
+    }
+
+    #[test]
+    fn validate_thread_count_accepts_one_to_the_maximum() {
+        assert_eq!(
+            common_validators::validate_thread_count(String::from("1")),
+            Ok(())
+        );
+        assert_eq!(
+            common_validators::validate_thread_count(MAX_THREAD_COUNT.to_string()),
+            Ok(())
+        );
+        assert_eq!(
+            common_validators::validate_thread_count(String::from("0")),
+            Err(String::from("0"))
+        );
+        assert_eq!(
+            common_validators::validate_thread_count((MAX_THREAD_COUNT + 1).to_string()),
+            Err((MAX_THREAD_COUNT + 1).to_string())
+        );
+    }
+
+    #[test]
+    fn validate_dns_port_rejects_port_zero_and_accepts_53() {
+        assert_eq!(
+            common_validators::validate_dns_port(String::from("0")),
+            Err(String::from("0"))
+        );
+        assert_eq!(
+            common_validators::validate_dns_port(String::from("53")),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn validate_proxied_domains_names_the_bad_domains() {
+        let result = common_validators::validate_proxied_domains(String::from(
+            "example.com,bad domain,sub.example.org,http://nope",
+        ));
+
+        assert_eq!(result, Err(String::from("bad domain;http://nope")));
+    }
+
+    #[test]
+    fn validate_reputation_lists_names_the_bad_lists() {
+        let result = common_validators::validate_reputation_lists(String::from(
+            "zen.example.org,bl example net",
+        ));
+
+        assert_eq!(result, Err(String::from("bl example net")));
+    }
+
+    #[test]
+    fn validate_socks_credentials_accepts_username_and_password() {
+        let result = common_validators::validate_socks_credentials(String::from("booga:pass:word"));
+
+        assert_eq!(Ok(()), result);
+    }
+
+    #[test]
+    fn validate_socks_credentials_rejects_missing_colon() {
+        let result = common_validators::validate_socks_credentials(String::from("booga"));
+
+        assert_eq!(Err(String::from("booga")), result);
+    }
+
+    #[test]
+    fn validate_socks_credentials_rejects_empty_username() {
+        let result = common_validators::validate_socks_credentials(String::from(":password"));
+
+        assert_eq!(Err(String::from(":password")), result);
+    }
+
+    #[test]
+    fn validate_socks_credentials_rejects_overlong_password() {
+        let credentials = format!("booga:{}", "x".repeat(256));
+
+        let result = common_validators::validate_socks_credentials(credentials.clone());
+
+        assert_eq!(Err(credentials), result);
+    }
+
+    #[test]
+    fn validate_payment_token_needs_a_symbol_an_address_and_sensible_decimals() {
+        let address = "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48";
+
+        assert_eq!(
+            common_validators::validate_payment_token(format!("USDC:{}:6", address)),
+            Ok(())
+        );
+        assert_eq!(
+            common_validators::validate_payment_token(format!("tMASQ:{}:36", address)),
+            Ok(())
+        );
+        assert_eq!(
+            common_validators::validate_payment_token(format!("USDC:{}:37", address)),
+            Err(format!("USDC:{}:37", address))
+        );
+        assert_eq!(
+            common_validators::validate_payment_token(format!("USDC:{}", address)),
+            Err(format!("USDC:{}", address))
+        );
+        assert_eq!(
+            common_validators::validate_payment_token("USDC:0x1234:6".to_string()),
+            Err("USDC:0x1234:6".to_string())
+        );
+    }
+
     #[test]
     fn validate_gas_price_zero() {
         let result = common_validators::validate_gas_price("0".to_string());
@@ -0,0 +1,34 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use crate::clipboard::Clipboard;
+use std::sync::Mutex;
+
+// Remembers everything set on it, and gives back the latest
+#[derive(Debug, Default)]
+pub struct ClipboardMock {
+    pub contents: Mutex<Vec<String>>,
+    pub get_error_opt: Option<String>,
+    pub set_error_opt: Option<String>,
+}
+
+impl Clipboard for ClipboardMock {
+    fn set(&self, text: &str) -> Result<(), String> {
+        if let Some(e) = &self.set_error_opt {
+            return Err(e.clone());
+        }
+        self.contents.lock().unwrap().push(text.to_string());
+        Ok(())
+    }
+
+    fn get(&self) -> Result<String, String> {
+        match &self.get_error_opt {
+            Some(e) => Err(e.clone()),
+            None => Ok(self
+                .contents
+                .lock()
+                .unwrap()
+                .last()
+                .cloned()
+                .unwrap_or_default()),
+        }
+    }
+}
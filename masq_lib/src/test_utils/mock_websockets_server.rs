@@ -289,9 +289,10 @@ mod tests {
     use super::*;
     use crate::messages::UiSetupResponseValueStatus::Set;
     use crate::messages::{
-        FromMessageBody, ToMessageBody, UiSetupResponse, UiSetupResponseValue, UiUnmarshalError,
-        NODE_UI_PROTOCOL,
+        FromMessageBody, ToMessageBody, UiSetupResponse, UiSetupResponseError,
+        UiSetupResponseValue, UiUnmarshalError, NODE_UI_PROTOCOL,
     };
+    use crate::shared_schema::ParamErrorCode::{Conflict, Invalid};
     use crate::test_utils::ui_connection::UiConnection;
     use crate::utils::find_free_port;
 
@@ -307,9 +308,10 @@ mod tests {
                 status: Set,
             }],
             errors: vec![
-                ("param1".to_string(), "reason1".to_string()),
-                ("param2".to_string(), "reason2".to_string()),
+                UiSetupResponseError::new("param1", Invalid, "reason1"),
+                UiSetupResponseError::new("param2", Conflict, "reason2"),
             ],
+            instance_opt: None,
         }
         .tmb(1);
         let second_expected_response = UiUnmarshalError {
@@ -332,9 +334,10 @@ mod tests {
                         status: Set,
                     }],
                     errors: vec![
-                        ("param1".to_string(), "reason1".to_string()),
-                        ("param2".to_string(), "reason2".to_string()),
+                        UiSetupResponseError::new("param1", Invalid, "reason1"),
+                        UiSetupResponseError::new("param2", Conflict, "reason2"),
                     ],
+                    instance_opt: None,
                 },
                 1234,
             )
@@ -356,9 +359,10 @@ mod tests {
                     status: Set,
                 }],
                 errors: vec![
-                    ("param1".to_string(), "reason1".to_string()),
-                    ("param2".to_string(), "reason2".to_string()),
-                ]
+                    UiSetupResponseError::new("param1", Invalid, "reason1"),
+                    UiSetupResponseError::new("param2", Conflict, "reason2"),
+                ],
+                instance_opt: None,
             }
         );
         assert_eq!(
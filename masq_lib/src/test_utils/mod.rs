@@ -1,5 +1,7 @@
 // Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
 
+#[cfg(any(test, feature = "clipboard"))]
+pub mod clipboard_mock;
 pub mod environment_guard;
 pub mod fake_stream_holder;
 pub mod mock_websockets_server;
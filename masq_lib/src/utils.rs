@@ -1,6 +1,7 @@
 // Copyright (c) 2019-2020, MASQ (https://masq.ai). All rights reserved.
 
 use lazy_static::lazy_static;
+use std::collections::BTreeSet;
 use std::io::ErrorKind;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener};
 use std::sync::Arc;
@@ -83,6 +84,49 @@ where
     }
 }
 
+// The most ports parse_port_list will produce, however wide the ranges it's given
+pub const MAX_PORT_LIST_LENGTH: usize = 32;
+
+// Parses a comma-separated list of ports and inclusive port ranges, such as "443,8443,9000-9005",
+// into a sorted list of distinct ports, no longer than MAX_PORT_LIST_LENGTH.
+pub fn parse_port_list(list: &str) -> Result<Vec<u16>, String> {
+    let parse_port = |port: &str| {
+        port.trim()
+            .parse::<u16>()
+            .map_err(|_| format!("'{}' is not a valid port number", port))
+    };
+    let mut ports: BTreeSet<u16> = BTreeSet::new();
+    for element in list.split(',') {
+        let (low, high) = match element.find('-') {
+            Some(idx) => {
+                let low = parse_port(&element[..idx])?;
+                let high = parse_port(&element[(idx + 1)..])?;
+                if low > high {
+                    return Err(format!("'{}' is not a valid port range", element));
+                }
+                (low, high)
+            }
+            None => {
+                let port = parse_port(element)?;
+                (port, port)
+            }
+        };
+        // Checked before expanding, so that 1-65535 doesn't build a 65535-port set first
+        if usize::from(high - low) >= MAX_PORT_LIST_LENGTH {
+            return Err(too_many_ports(list));
+        }
+        ports.extend(low..=high);
+        if ports.len() > MAX_PORT_LIST_LENGTH {
+            return Err(too_many_ports(list));
+        }
+    }
+    Ok(ports.into_iter().collect())
+}
+
+fn too_many_ports(list: &str) -> String {
+    format!("'{}' names more than {} ports", list, MAX_PORT_LIST_LENGTH)
+}
+
 pub fn running_test() {
     unsafe {
         RUNNING_TEST = true;
@@ -102,6 +146,67 @@ pub fn exit_process(code: i32, message: &str) {
 mod tests {
     use super::*;
 
+    #[test]
+    fn parse_port_list_handles_single_ports_and_ranges() {
+        let result = parse_port_list("443,8443,9000-9002, 9001").unwrap();
+
+        assert_eq!(result, vec![443, 8443, 9000, 9001, 9002]);
+    }
+
+    #[test]
+    fn parse_port_list_sorts_and_deduplicates() {
+        let result = parse_port_list("9001,9000-9002,443,9001-9001,443").unwrap();
+
+        assert_eq!(result, vec![443, 9000, 9001, 9002]);
+    }
+
+    #[test]
+    fn parse_port_list_accepts_exactly_the_maximum_number_of_ports() {
+        let result = parse_port_list("2000-2031,2000").unwrap();
+
+        assert_eq!(result.len(), MAX_PORT_LIST_LENGTH);
+    }
+
+    #[test]
+    fn parse_port_list_refuses_a_range_wider_than_the_maximum() {
+        let result = parse_port_list("1-65535");
+
+        assert_eq!(
+            result,
+            Err("'1-65535' names more than 32 ports".to_string())
+        );
+    }
+
+    #[test]
+    fn parse_port_list_refuses_ranges_that_add_up_to_more_than_the_maximum() {
+        let result = parse_port_list("2000-2030,3000,3001");
+
+        assert_eq!(
+            result,
+            Err("'2000-2030,3000,3001' names more than 32 ports".to_string())
+        );
+    }
+
+    #[test]
+    fn parse_port_list_complains_about_bad_port() {
+        let result = parse_port_list("443,booga");
+
+        assert_eq!(
+            result,
+            Err("'booga' is not a valid port number".to_string())
+        );
+    }
+
+    #[test]
+    fn parse_port_list_complains_about_backwards_range() {
+        let result = parse_port_list("9005-9000");
+
+        assert_eq!(
+            result,
+            Err("'9005-9000' is not a valid port range".to_string())
+        );
+    }
+
     #[test]
     fn index_of_fails_to_find_nonexistent_needle_in_haystack() {
         let result = index_of("haystack".as_bytes(), "needle".as_bytes());
@@ -254,5 +254,6 @@ fn from_masq_node_to_node_record(masq_node: &dyn MASQNode) -> NodeRecord {
         },
         signed_gossip: agr.signed_gossip.clone(),
         signature: agr.signature,
+        node_addr_signature_opt: agr.node_addr_signature_opt,
     }
 }
@@ -1,10 +1,11 @@
 // Copyright (c) 2017-2019, Substratum LLC (https://substratum.net) and/or its affiliates. All rights reserved.
 
 use crate::masq_node::MASQNode;
+use masq_lib::exit_policy::ExitPolicy;
 use node_lib::neighborhood::node_record::NodeRecordInner_0v1;
 use node_lib::neighborhood::AccessibleGossipRecord;
 use node_lib::sub_lib::cryptde::{CryptData, PlainData};
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::io::{ErrorKind, Read, Write};
 use std::net::TcpStream;
 use std::time::{Duration, Instant};
@@ -76,8 +77,18 @@ impl From<&dyn MASQNode> for AccessibleGossipRecord {
                 accepts_connections: masq_node.accepts_connections(),
                 routes_data: masq_node.routes_data(),
                 version: 0,
+                exit_policy: ExitPolicy::default(),
+                key_succession_opt: None,
+                features: BTreeSet::new(),
+                kem_public_key_opt: None,
+                reputation_score_opt: None,
+                wallet_change_opt: None,
+                link_performance: BTreeMap::new(),
+                attestations: vec![],
+                tombstone: false,
             },
             node_addr_opt: Some(masq_node.node_addr()),
+            node_addr_signature_opt: None,
             signed_gossip: PlainData::new(b""),
             signature: CryptData::new(b""),
         };
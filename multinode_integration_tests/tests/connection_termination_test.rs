@@ -339,6 +339,7 @@ fn create_request_icp(
                 target_port: server.socket_addr().port(),
                 protocol: ProxyProtocol::HTTP,
                 originator_public_key: originating_node.main_public_key().clone(),
+                is_resumption: false,
             },
         )),
         exit_node.main_public_key(),
@@ -384,6 +385,7 @@ fn create_meaningless_icp(
                 target_port: socket_addr.port(),
                 protocol: ProxyProtocol::HTTP,
                 originator_public_key: originating_node.main_public_key().clone(),
+                is_resumption: false,
             },
         )),
         exit_node.main_public_key(),
@@ -474,6 +476,7 @@ fn create_client_drop_report(
             target_port: 80,
             protocol: ProxyProtocol::HTTP,
             originator_public_key: originating_node.main_public_key().clone(),
+            is_resumption: false,
         },
     ));
 
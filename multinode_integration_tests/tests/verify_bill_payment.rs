@@ -14,6 +14,7 @@ use node_lib::blockchain::bip32::Bip32ECKeyPair;
 use node_lib::blockchain::blockchain_interface::{
     contract_address, BlockchainInterface, BlockchainInterfaceNonClandestine,
 };
+use node_lib::blockchain::payment_adapter::make_payment_adapter;
 use node_lib::blockchain::raw_transaction::RawTransaction;
 use node_lib::database::db_initializer::{DbInitializer, DbInitializerReal};
 use node_lib::sub_lib::wallet::Wallet;
@@ -56,8 +57,12 @@ fn verify_bill_payment() {
         "Ganache is not as predictable as we thought: Update blockchain_interface::MULTINODE_CONTRACT_ADDRESS with {:?}",
         contract_addr
     );
-    let blockchain_interface =
-        BlockchainInterfaceNonClandestine::new(http, _event_loop_handle, cluster.chain_id);
+    let blockchain_interface = BlockchainInterfaceNonClandestine::new(
+        http,
+        _event_loop_handle,
+        cluster.chain_id,
+        make_payment_adapter(cluster.chain_id, None),
+    );
     assert_balances(
         &contract_owner_wallet,
         &blockchain_interface,
@@ -0,0 +1,90 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+
+// How long it takes to index a neighborhood of a few thousand Nodes and find routes through it.
+// Run with: cargo bench --bench route_search
+use node_lib::neighborhood::neighborhood_database::NeighborhoodDatabase;
+use node_lib::neighborhood::route_search::{RouteIndex, RouteQuery, DEFAULT_ROUTE_SEARCH_THREADS};
+use node_lib::sub_lib::cryptde::PublicKey;
+use node_lib::test_utils::neighborhood_test_utils::{db_from_node, make_node_record};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const NODE_COUNT: u16 = 3000;
+const ROUNDS: u32 = 10;
+
+// A ring of Nodes, each of which is also neighbors with the Nodes a few places along the ring
+fn ring_db(node_count: u16) -> (NeighborhoodDatabase, Vec<PublicKey>) {
+    let root = make_node_record(1000, true);
+    let mut db = db_from_node(&root);
+    let mut keys = vec![root.public_key().clone()];
+    (1..node_count).for_each(|n| {
+        keys.push(db.add_node(make_node_record(1000 + n, true)).unwrap());
+    });
+    (0..keys.len()).for_each(|n| {
+        vec![1, 7, 31].into_iter().for_each(|distance| {
+            db.add_arbitrary_full_neighbor(&keys[n], &keys[(n + distance) % keys.len()]);
+        })
+    });
+    (db, keys)
+}
+
+fn query(
+    index: &RouteIndex,
+    prefix: &[&PublicKey],
+    target_opt: Option<&PublicKey>,
+    hops_remaining: usize,
+    target_always_enterable: bool,
+) -> RouteQuery {
+    RouteQuery {
+        prefix: prefix
+            .iter()
+            .map(|key| index.position(key).unwrap())
+            .collect(),
+        target_opt: target_opt.map(|key| index.position(key).unwrap()),
+        hops_remaining,
+        target_always_enterable,
+    }
+}
+
+fn time<F: FnMut() -> usize>(label: &str, mut f: F) {
+    let mut total = Duration::from_secs(0);
+    let mut found = 0;
+    (0..ROUNDS).for_each(|_| {
+        let start = Instant::now();
+        found = f();
+        total += start.elapsed();
+    });
+    println!(
+        "{:40}{:>12?} per round ({} found)",
+        label,
+        total / ROUNDS,
+        found
+    );
+}
+
+fn main() {
+    let (db, keys) = ring_db(NODE_COUNT);
+    time(&format!("index {} Nodes", NODE_COUNT), || {
+        RouteIndex::new(&db).node_count()
+    });
+    let index = Arc::new(RouteIndex::new(&db));
+    time("search 3 hops out", || {
+        RouteIndex::search(
+            &index,
+            query(&index, &[&keys[0]], None, 3, false),
+            DEFAULT_ROUTE_SEARCH_THREADS,
+        )
+        .len()
+    });
+    time("search 3 hops back", || {
+        RouteIndex::search(
+            &index,
+            query(&index, &[&keys[93]], Some(&keys[0]), 3, true),
+            DEFAULT_ROUTE_SEARCH_THREADS,
+        )
+        .len()
+    });
+    time("search 3 hops out on one thread", || {
+        RouteIndex::search(&index, query(&index, &[&keys[0]], None, 3, false), 1).len()
+    });
+}
@@ -0,0 +1,26 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use node_fuzz::{local_cryptde, local_database, GossipInput};
+use node_lib::neighborhood::accessible_gossip_records;
+use node_lib::neighborhood::gossip_acceptor::{GossipAcceptor, GossipAcceptorReal};
+use node_lib::sub_lib::logger::Logger;
+use std::net::SocketAddr;
+use std::str::FromStr;
+
+// Gossip goes through the same validation the Neighborhood gives it, and whatever survives is
+// offered to the GossipAcceptor, which may accept it, ignore it or ban its source, but not crash.
+fuzz_target!(|input: GossipInput| {
+    let cryptde = local_cryptde();
+    let gossip_source = SocketAddr::from_str("2.3.4.5:2345").unwrap();
+    let logger = Logger::new("GossipFuzzer");
+    let agrs = match accessible_gossip_records(input.to_gossip(), gossip_source, &cryptde, &logger)
+    {
+        Ok(agrs) => agrs,
+        Err(_) => return,
+    };
+    let mut database = local_database(&cryptde);
+    let _ = GossipAcceptorReal::new(&cryptde).handle(&mut database, agrs, gossip_source);
+});
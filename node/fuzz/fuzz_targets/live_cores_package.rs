@@ -0,0 +1,31 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use node_fuzz::{local_cryptde, LiveCoresPackageInput};
+use node_lib::hopper::live_cores_package::LiveCoresPackage;
+use std::net::SocketAddr;
+use std::str::FromStr;
+
+// Follows a package through everything the Hopper does with one: a package that can't be
+// decoded, routed or consumed must come back as an error.
+fuzz_target!(|input: LiveCoresPackageInput| {
+    let cryptde = local_cryptde();
+    let data = input.to_crypt_data(&cryptde);
+    let (package, _) = match LiveCoresPackage::decode_stamped(&cryptde, &data) {
+        Ok(decoded) => decoded,
+        Err(_) => return,
+    };
+    if package.route.next_hop(&cryptde).is_err() {
+        return;
+    }
+    let _ = package.to_expired(
+        SocketAddr::from_str("1.2.3.4:5678").unwrap(),
+        &cryptde,
+        &cryptde,
+    );
+    if let Ok((next_hop, next_live)) = package.into_next_live(&cryptde) {
+        let _ = next_live.encode(&cryptde, &next_hop.public_key);
+    }
+});
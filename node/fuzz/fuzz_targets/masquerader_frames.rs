@@ -0,0 +1,27 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use node_fuzz::StreamPiece;
+use node_lib::discriminator::DiscriminatorFactory;
+use node_lib::http_request_start_finder::HttpRequestDiscriminatorFactory;
+use node_lib::json_discriminator_factory::JsonDiscriminatorFactory;
+use node_lib::tls_discriminator_factory::TlsDiscriminatorFactory;
+
+// Whatever arrives on a clandestine port, in whatever pieces, every Discriminator must either
+// unmask it or throw it away.
+fuzz_target!(|pieces: Vec<StreamPiece>| {
+    let factories: Vec<Box<dyn DiscriminatorFactory>> = vec![
+        Box::new(JsonDiscriminatorFactory::new()),
+        Box::new(TlsDiscriminatorFactory::new()),
+        Box::new(HttpRequestDiscriminatorFactory::new()),
+    ];
+    factories.iter().for_each(|factory| {
+        let mut discriminator = factory.make();
+        pieces.iter().for_each(|piece| {
+            discriminator.add_data(&piece.to_bytes());
+            while discriminator.take_chunk().is_some() {}
+        });
+    });
+});
@@ -0,0 +1,339 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+
+// Structured inputs for the fuzz targets. Purely random bytes almost never get past the first
+// layer of a decoder, so each input here is built the way another Node would build it, with
+// enough left to chance that it can still be malformed anywhere.
+
+use arbitrary::Arbitrary;
+use masq_lib::exit_policy::ExitPolicy;
+use masq_lib::test_utils::utils::DEFAULT_CHAIN_ID;
+use node_lib::hopper::live_cores_package::LiveCoresPackage;
+use node_lib::json_masquerader::JsonMasquerader;
+use node_lib::masquerader::Masquerader;
+use node_lib::neighborhood::gossip::{GossipNodeRecord, Gossip_0v1};
+use node_lib::neighborhood::neighborhood_database::NeighborhoodDatabase;
+use node_lib::neighborhood::node_record::NodeRecordInner_0v1;
+use node_lib::sub_lib::cryptde::{encodex, CryptDE, CryptData, PlainData, PublicKey};
+use node_lib::sub_lib::cryptde_null::CryptDENull;
+use node_lib::sub_lib::dispatcher::Component;
+use node_lib::sub_lib::hop::LiveHop;
+use node_lib::sub_lib::hopper::MessageType;
+use node_lib::sub_lib::link_benchmark::LinkPerformance;
+use node_lib::sub_lib::neighborhood::{NeighborhoodMode, RatePack, DEFAULT_RATE_PACK};
+use node_lib::sub_lib::node_addr::NodeAddr;
+use node_lib::sub_lib::route::Route;
+use node_lib::sub_lib::wallet::Wallet;
+use std::net::{IpAddr, Ipv4Addr};
+
+// Few enough keys that records keep running into one another and into the Node under test
+pub fn key(n: u8) -> PublicKey {
+    PublicKey::new(&[n % 8; 4])
+}
+
+// CryptDENull, so that the fuzzer spends its time on the decoders rather than on the cryptography
+pub fn cryptde_for(n: u8) -> CryptDENull {
+    CryptDENull::from(&key(n), DEFAULT_CHAIN_ID)
+}
+
+// The Node under test is always Node 0
+pub fn local_cryptde() -> CryptDENull {
+    cryptde_for(0)
+}
+
+pub fn local_database(cryptde: &dyn CryptDE) -> NeighborhoodDatabase {
+    NeighborhoodDatabase::new(
+        cryptde.public_key(),
+        NeighborhoodMode::Standard(
+            NodeAddr::new(&IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1)), &[1111]),
+            vec![],
+            DEFAULT_RATE_PACK,
+        ),
+        Wallet::new("0x0101010101010101010101010101010101010101"),
+        cryptde,
+    )
+}
+
+fn mangle(data: &[u8], mangles: &[(u16, u8)]) -> Vec<u8> {
+    let mut data = data.to_vec();
+    if !data.is_empty() {
+        let len = data.len();
+        mangles
+            .iter()
+            .for_each(|(index, byte)| data[*index as usize % len] = *byte);
+    }
+    data
+}
+
+#[derive(Arbitrary, Debug)]
+pub enum StreamPiece {
+    Raw(Vec<u8>),
+    // A clandestine frame as another Node would send it
+    Masked(Vec<u8>),
+    Truncated(Vec<u8>, u16),
+    Mangled(Vec<u8>, Vec<(u16, u8)>),
+    TlsRecord(u8, u16, Vec<u8>),
+    HttpRequest {
+        content_length: u32,
+        chunked: bool,
+        body: Vec<u8>,
+    },
+    HttpChunk(u32, Vec<u8>),
+}
+
+impl StreamPiece {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mask = |data: &[u8]| {
+            JsonMasquerader::new()
+                .mask(data)
+                .expect("JsonMasquerader couldn't mask")
+        };
+        match self {
+            StreamPiece::Raw(data) => data.clone(),
+            StreamPiece::Masked(data) => mask(data),
+            StreamPiece::Truncated(data, len) => {
+                let mut masked = mask(data);
+                masked.truncate(*len as usize);
+                masked
+            }
+            StreamPiece::Mangled(data, mangles) => mangle(&mask(data), mangles),
+            StreamPiece::TlsRecord(content_type, len, data) => {
+                let mut record = vec![0x14 + (content_type % 4), 0x03, 0x03];
+                record.extend_from_slice(&len.to_be_bytes());
+                record.extend_from_slice(data);
+                record
+            }
+            StreamPiece::HttpRequest {
+                content_length,
+                chunked,
+                body,
+            } => {
+                let mut request = format!(
+                    "POST /index.html HTTP/1.1\r\nHost: example.com\r\nContent-Length: {}\r\n",
+                    content_length
+                )
+                .into_bytes();
+                if *chunked {
+                    request.extend_from_slice(b"Transfer-Encoding: chunked\r\n");
+                }
+                request.extend_from_slice(b"\r\n");
+                request.extend_from_slice(body);
+                request
+            }
+            StreamPiece::HttpChunk(len, data) => {
+                let mut chunk = format!("{:X}\r\n", len).into_bytes();
+                chunk.extend_from_slice(data);
+                chunk.extend_from_slice(b"\r\n");
+                chunk
+            }
+        }
+    }
+}
+
+#[derive(Arbitrary, Debug)]
+pub struct NodeAddrInput {
+    ip: [u8; 4],
+    ports: Vec<u16>,
+}
+
+impl NodeAddrInput {
+    pub fn to_node_addr(&self) -> NodeAddr {
+        NodeAddr::new(&IpAddr::V4(Ipv4Addr::from(self.ip)), &self.ports)
+    }
+}
+
+#[derive(Arbitrary, Debug)]
+pub struct NodeRecordInput {
+    key: u8,
+    wallet: [u8; 20],
+    rate_pack: [u64; 4],
+    neighbors: Vec<u8>,
+    accepts_connections: bool,
+    routes_data: bool,
+    version: u32,
+    features: Vec<String>,
+    reputation_score_opt: Option<u8>,
+    link_performance: Vec<(u8, u32, u32)>,
+    tombstone: bool,
+}
+
+impl NodeRecordInput {
+    pub fn to_inner(&self) -> NodeRecordInner_0v1 {
+        let wallet_address: String = self
+            .wallet
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        NodeRecordInner_0v1 {
+            public_key: key(self.key),
+            earning_wallet: Wallet::new(&format!("0x{}", wallet_address)),
+            rate_pack: RatePack {
+                routing_byte_rate: self.rate_pack[0],
+                routing_service_rate: self.rate_pack[1],
+                exit_byte_rate: self.rate_pack[2],
+                exit_service_rate: self.rate_pack[3],
+            },
+            neighbors: self.neighbors.iter().map(|n| key(*n)).collect(),
+            accepts_connections: self.accepts_connections,
+            routes_data: self.routes_data,
+            version: self.version,
+            exit_policy: ExitPolicy::default(),
+            key_succession_opt: None,
+            features: self.features.iter().cloned().collect(),
+            kem_public_key_opt: None,
+            reputation_score_opt: self.reputation_score_opt,
+            wallet_change_opt: None,
+            link_performance: self
+                .link_performance
+                .iter()
+                .map(|(n, latency_ms, throughput_kbps)| {
+                    (
+                        key(*n),
+                        LinkPerformance {
+                            latency_ms: *latency_ms,
+                            throughput_kbps: *throughput_kbps,
+                        },
+                    )
+                })
+                .collect(),
+            tombstone: self.tombstone,
+        }
+    }
+}
+
+#[derive(Arbitrary, Debug)]
+pub enum GossipRecordInput {
+    Garbage {
+        signed_data: Vec<u8>,
+        signature: Vec<u8>,
+        node_addr_opt: Option<NodeAddrInput>,
+    },
+    Signed {
+        record: NodeRecordInput,
+        node_addr_opt: Option<NodeAddrInput>,
+        // Signed by some other Node than the one it describes
+        forged: bool,
+    },
+}
+
+impl GossipRecordInput {
+    pub fn to_gossip_node_record(&self) -> GossipNodeRecord {
+        match self {
+            GossipRecordInput::Garbage {
+                signed_data,
+                signature,
+                node_addr_opt,
+            } => GossipNodeRecord {
+                signed_data: PlainData::new(signed_data),
+                signature: CryptData::new(signature),
+                node_addr_opt: node_addr_opt.as_ref().map(NodeAddrInput::to_node_addr),
+                node_addr_signature_opt: None,
+            },
+            GossipRecordInput::Signed {
+                record,
+                node_addr_opt,
+                forged,
+            } => {
+                let signer = cryptde_for(if *forged {
+                    record.key.wrapping_add(1)
+                } else {
+                    record.key
+                });
+                GossipNodeRecord::from((
+                    record.to_inner(),
+                    node_addr_opt.as_ref().map(NodeAddrInput::to_node_addr),
+                    &signer as &dyn CryptDE,
+                ))
+            }
+        }
+    }
+}
+
+#[derive(Arbitrary, Debug)]
+pub struct GossipInput {
+    records: Vec<GossipRecordInput>,
+}
+
+impl GossipInput {
+    pub fn to_gossip(&self) -> Gossip_0v1 {
+        Gossip_0v1 {
+            node_records: self
+                .records
+                .iter()
+                .map(GossipRecordInput::to_gossip_node_record)
+                .collect(),
+        }
+    }
+}
+
+#[derive(Arbitrary, Debug)]
+pub enum HopInput {
+    Garbage(Vec<u8>),
+    // Readable by the Node under test, like the top hop of every package it gets
+    Live { key: u8, component: u8, timed: bool },
+}
+
+impl HopInput {
+    pub fn to_crypt_data(&self, cryptde: &dyn CryptDE) -> CryptData {
+        match self {
+            HopInput::Garbage(data) => CryptData::new(data),
+            HopInput::Live {
+                key: n,
+                component,
+                timed,
+            } => {
+                let component = Component::values()[*component as usize % 4];
+                let mut hop = LiveHop::new(&key(*n), None, component);
+                if *timed {
+                    hop = hop.with_timing_key(&key(n.wrapping_add(1)));
+                }
+                hop.encode(cryptde.public_key(), cryptde)
+                    .expect("Couldn't encode LiveHop")
+            }
+        }
+    }
+}
+
+#[derive(Arbitrary, Debug)]
+pub enum PayloadInput {
+    Garbage(Vec<u8>),
+    Gossip(GossipInput),
+}
+
+#[derive(Arbitrary, Debug)]
+pub struct LiveCoresPackageInput {
+    hops: Vec<HopInput>,
+    payload: PayloadInput,
+    hop_timings: Vec<Vec<u8>>,
+    mangles: Vec<(u16, u8)>,
+}
+
+impl LiveCoresPackageInput {
+    // Encrypted to the Node under test, as the Node before it would have sent it
+    pub fn to_crypt_data(&self, cryptde: &dyn CryptDE) -> CryptData {
+        let route = Route {
+            hops: self
+                .hops
+                .iter()
+                .map(|hop| hop.to_crypt_data(cryptde))
+                .collect(),
+        };
+        let payload = match &self.payload {
+            PayloadInput::Garbage(data) => CryptData::new(data),
+            PayloadInput::Gossip(gossip) => {
+                let message_type: MessageType = gossip.to_gossip().into();
+                encodex(cryptde, cryptde.public_key(), &message_type)
+                    .expect("Couldn't encode Gossip")
+            }
+        };
+        let mut package = LiveCoresPackage::new(route, payload);
+        package.hop_timings = self
+            .hop_timings
+            .iter()
+            .map(|timing| CryptData::new(timing))
+            .collect();
+        let encoded = package
+            .encode(cryptde, cryptde.public_key())
+            .expect("Couldn't encode LiveCoresPackage");
+        CryptData::new(&mangle(encoded.as_slice(), &self.mangles))
+    }
+}
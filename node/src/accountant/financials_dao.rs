@@ -0,0 +1,224 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use crate::accountant::payable_dao::{self, PayableAccount};
+use crate::accountant::receivable_dao::{self, ReceivableAccount};
+use crate::database::connection_wrapper::ConnectionWrapper;
+use crate::database::dao_utils::DaoFactoryReal;
+use std::time::SystemTime;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FinancialsSnapshot {
+    pub payables: Vec<PayableAccount>,
+    pub total_payable: u64,
+    pub receivables: Vec<ReceivableAccount>,
+    pub total_receivable: u64,
+}
+
+pub trait FinancialsDao: Send {
+    // The top payables and receivables, and the totals, as of a single moment, however much
+    // scans and payments are changing them in the meantime
+    fn snapshot(
+        &mut self,
+        now: SystemTime,
+        payable_minimum_amount: u64,
+        payable_maximum_age: u64,
+        receivable_minimum_amount: u64,
+        receivable_maximum_age: u64,
+    ) -> FinancialsSnapshot;
+}
+
+pub trait FinancialsDaoFactory {
+    fn make(&self) -> Box<dyn FinancialsDao>;
+}
+
+impl FinancialsDaoFactory for DaoFactoryReal {
+    fn make(&self) -> Box<dyn FinancialsDao> {
+        Box::new(FinancialsDaoReal::new(self.make_connection()))
+    }
+}
+
+pub struct FinancialsDaoReal {
+    conn: Box<dyn ConnectionWrapper>,
+    between_reads: Box<dyn FnMut() + Send>,
+}
+
+impl FinancialsDao for FinancialsDaoReal {
+    // Everything is read in one transaction, which SQLite keeps to one snapshot of the database
+    // from its first read to its end. It's only read, so it's rolled back when it's dropped.
+    fn snapshot(
+        &mut self,
+        now: SystemTime,
+        payable_minimum_amount: u64,
+        payable_maximum_age: u64,
+        receivable_minimum_amount: u64,
+        receivable_maximum_age: u64,
+    ) -> FinancialsSnapshot {
+        let tx = self
+            .conn
+            .transaction()
+            .unwrap_or_else(|e| panic!("Couldn't start a financials snapshot: {:?}", e));
+        let payables = payable_dao::top_records(
+            |sql| tx.prepare(sql),
+            now,
+            payable_minimum_amount,
+            payable_maximum_age,
+        );
+        let total_payable = payable_dao::total(|sql| tx.prepare(sql));
+        (self.between_reads)();
+        FinancialsSnapshot {
+            payables,
+            total_payable,
+            receivables: receivable_dao::top_records(
+                |sql| tx.prepare(sql),
+                now,
+                receivable_minimum_amount,
+                receivable_maximum_age,
+            ),
+            total_receivable: receivable_dao::total(|sql| tx.prepare(sql)),
+        }
+    }
+}
+
+impl FinancialsDaoReal {
+    pub fn new(conn: Box<dyn ConnectionWrapper>) -> Self {
+        Self {
+            conn,
+            between_reads: Box::new(|| ()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::accountant::payable_dao::{PayableDao, PayableDaoReal};
+    use crate::accountant::receivable_dao::{ReceivableDao, ReceivableDaoReal};
+    use crate::database::db_initializer::{DbInitializer, DbInitializerReal};
+    use crate::test_utils::make_wallet;
+    use masq_lib::test_utils::utils::{ensure_node_home_directory_exists, DEFAULT_CHAIN_ID};
+    use rusqlite::NO_PARAMS;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn a_snapshot_doesnt_see_changes_committed_between_its_reads() {
+        let home_dir = ensure_node_home_directory_exists(
+            "financials_dao",
+            "a_snapshot_doesnt_see_changes_committed_between_its_reads",
+        );
+        let db_initializer = DbInitializerReal::new();
+        let connect = || {
+            db_initializer
+                .initialize(&home_dir, DEFAULT_CHAIN_ID, true)
+                .unwrap()
+        };
+        let payable_dao = PayableDaoReal::new(connect());
+        let receivable_dao = ReceivableDaoReal::new(connect());
+        payable_dao
+            .more_money_payable(&make_wallet("creditor"), 1000)
+            .unwrap();
+        receivable_dao
+            .more_money_receivable(&make_wallet("debtor"), 2000)
+            .unwrap();
+        let mut subject = FinancialsDaoReal::new(connect());
+        let writer_payable_dao = PayableDaoReal::new(connect());
+        let writer_receivable_dao = ReceivableDaoReal::new(connect());
+        let writes_done = Arc::new(Mutex::new(false));
+        let writes_done_inner = writes_done.clone();
+        subject.between_reads = Box::new(move || {
+            writer_payable_dao
+                .more_money_payable(&make_wallet("creditor"), 500)
+                .unwrap();
+            writer_receivable_dao
+                .more_money_receivable(&make_wallet("debtor"), 700)
+                .unwrap();
+            *writes_done_inner.lock().unwrap() = true;
+        });
+
+        let result = subject.snapshot(SystemTime::now(), 0, 1_000_000, 0, 1_000_000);
+
+        assert_eq!(*writes_done.lock().unwrap(), true);
+        assert_eq!(result.total_payable, 1000);
+        assert_eq!(
+            result
+                .payables
+                .iter()
+                .map(|account| (account.wallet.clone(), account.balance))
+                .collect::<Vec<_>>(),
+            vec![(make_wallet("creditor"), 1000)]
+        );
+        assert_eq!(result.total_receivable, 2000);
+        assert_eq!(
+            result
+                .receivables
+                .iter()
+                .map(|account| (account.wallet.clone(), account.balance))
+                .collect::<Vec<_>>(),
+            vec![(make_wallet("debtor"), 2000)]
+        );
+        subject.between_reads = Box::new(|| ());
+        let later = subject.snapshot(SystemTime::now(), 0, 1_000_000, 0, 1_000_000);
+        assert_eq!(later.total_payable, 1500);
+        assert_eq!(later.total_receivable, 2700);
+    }
+
+    #[test]
+    fn a_snapshot_doesnt_see_changes_committed_while_its_being_read() {
+        let home_dir = ensure_node_home_directory_exists(
+            "financials_dao",
+            "a_snapshot_doesnt_see_changes_committed_while_its_being_read",
+        );
+        let db_initializer = DbInitializerReal::new();
+        let connect = || {
+            db_initializer
+                .initialize(&home_dir, DEFAULT_CHAIN_ID, true)
+                .unwrap()
+        };
+        let payable_dao = PayableDaoReal::new(connect());
+        let receivable_dao = ReceivableDaoReal::new(connect());
+        payable_dao
+            .more_money_payable(&make_wallet("creditor"), 1000)
+            .unwrap();
+        receivable_dao
+            .more_money_receivable(&make_wallet("debtor"), 2000)
+            .unwrap();
+        let mut conn = connect();
+        let tx = conn.transaction().unwrap();
+        let payable_before: i64 = tx
+            .query_row("select sum(balance) from payable", NO_PARAMS, |row| {
+                row.get(0)
+            })
+            .unwrap();
+        payable_dao
+            .more_money_payable(&make_wallet("creditor"), 500)
+            .unwrap();
+        let payable_during: i64 = tx
+            .query_row("select sum(balance) from payable", NO_PARAMS, |row| {
+                row.get(0)
+            })
+            .unwrap();
+        drop(tx);
+        let mut subject = FinancialsDaoReal::new(conn);
+
+        let result = subject.snapshot(SystemTime::now(), 0, 1_000_000, 0, 1_000_000);
+
+        assert_eq!(payable_before, 1000);
+        assert_eq!(payable_during, 1000);
+        assert_eq!(result.total_payable, 1500);
+        assert_eq!(
+            result
+                .payables
+                .iter()
+                .map(|account| (account.wallet.clone(), account.balance))
+                .collect::<Vec<_>>(),
+            vec![(make_wallet("creditor"), 1500)]
+        );
+        assert_eq!(result.total_receivable, 2000);
+        assert_eq!(
+            result
+                .receivables
+                .iter()
+                .map(|account| (account.wallet.clone(), account.balance))
+                .collect::<Vec<_>>(),
+            vec![(make_wallet("debtor"), 2000)]
+        );
+    }
+}
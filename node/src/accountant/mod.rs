@@ -1,14 +1,22 @@
 // Copyright (c) 2017-2019, Substratum LLC (https://substratum.net) and/or its affiliates. All rights reserved.
 
+pub mod financials_dao;
+pub mod neighbor_earnings;
 pub mod payable_dao;
+pub mod payment_journal;
 pub mod receivable_dao;
+pub mod usage_history_dao;
 
 #[cfg(test)]
 pub mod test_utils;
 
+use crate::accountant::financials_dao::{FinancialsDao, FinancialsDaoFactory};
+use crate::accountant::neighbor_earnings::EARNINGS_CACHE;
 use crate::accountant::payable_dao::{PayableAccount, PayableDaoFactory, Payment};
+use crate::accountant::payment_journal::{PaymentJournal, PaymentJournalReal};
 use crate::accountant::receivable_dao::{ReceivableAccount, ReceivableDaoFactory};
-use crate::banned_dao::{BannedDao, BannedDaoFactory};
+use crate::accountant::usage_history_dao::{DailyUsage, UsageHistoryDao, UsageHistoryDaoFactory};
+use crate::banned_dao::{BannedDao, BannedDaoFactory, BAN_CACHE};
 use crate::blockchain::blockchain_bridge::RetrieveTransactions;
 use crate::blockchain::blockchain_interface::{BlockchainError, Transaction};
 use crate::bootstrapper::BootstrapperConfig;
@@ -18,15 +26,25 @@ use crate::db_config::persistent_configuration::{
 };
 use crate::sub_lib::accountant::AccountantConfig;
 use crate::sub_lib::accountant::AccountantSubs;
+use crate::sub_lib::accountant::PaymentOverdue_0v1;
+use crate::sub_lib::accountant::ReportEarningWalletChangeMessage;
 use crate::sub_lib::accountant::ReportExitServiceConsumedMessage;
 use crate::sub_lib::accountant::ReportExitServiceProvidedMessage;
 use crate::sub_lib::accountant::ReportRoutingServiceConsumedMessage;
 use crate::sub_lib::accountant::ReportRoutingServiceProvidedMessage;
+use crate::sub_lib::attestation::score_with_attestations;
 use crate::sub_lib::blockchain_bridge::ReportAccountsPayable;
+use crate::sub_lib::clock::{Clock, ClockReal};
+use crate::sub_lib::data_cap;
+use crate::sub_lib::data_cap::{DataCapConfig, DataCapStatus, DataCapTracker, THROTTLE_PERCENT};
+use crate::sub_lib::hopper::ExpiredCoresPackage;
 use crate::sub_lib::logger::Logger;
+use crate::sub_lib::metrics;
 use crate::sub_lib::peer_actors::{BindMessage, StartMessage};
+use crate::sub_lib::redacted::Redacted;
 use crate::sub_lib::utils::NODE_MAILBOX_CAPACITY;
 use crate::sub_lib::wallet::Wallet;
+use crate::trust_dao::{TrustDao, TrustDaoFactory, TrustEvidence, TRUST_CACHE};
 use actix::Actor;
 use actix::Addr;
 use actix::AsyncContext;
@@ -34,22 +52,32 @@ use actix::Context;
 use actix::Handler;
 use actix::Message;
 use actix::Recipient;
+use chrono::{DateTime, Local, Utc};
 use futures::future::Future;
 use itertools::Itertools;
 use lazy_static::lazy_static;
 use masq_lib::messages::UiMessageError::UnexpectedMessage;
 use masq_lib::messages::{FromMessageBody, ToMessageBody, UiFinancialsRequest, UiMessageError};
-use masq_lib::messages::{UiFinancialsResponse, UiPayableAccount, UiReceivableAccount};
-use masq_lib::ui_gateway::MessageTarget::ClientId;
+use masq_lib::messages::{
+    UiDataCapRequest, UiDataCapResponse, UiFinancialsResponse, UiPayableAccount,
+    UiPaymentBroadcast, UiPaymentOverdueBroadcast, UiPaymentReceivedBroadcast, UiReceivableAccount,
+    UiUsageDay, UiUsageHistoryRequest, UiUsageHistoryResponse,
+};
+use masq_lib::ui_gateway::MessageTarget::{AllClients, ClientId};
 use masq_lib::ui_gateway::{NodeFromUiMessage, NodeToUiMessage};
 use payable_dao::PayableDao;
 use receivable_dao::ReceivableDao;
+use std::collections::HashMap;
+use std::sync::Arc;
 use std::thread;
 use std::time::{Duration, SystemTime};
 
 pub const CRASH_KEY: &str = "ACCOUNTANT";
 pub const DEFAULT_PAYABLE_SCAN_INTERVAL: u64 = 3600; // one hour
 pub const DEFAULT_PAYMENT_RECEIVED_SCAN_INTERVAL: u64 = 3600; // one hour
+const USAGE_RECORDING_INTERVAL: Duration = Duration::from_secs(600);
+// How long a delinquent Node has between its payment-overdue notice and its ban
+pub const DUNNING_PERIOD: Duration = Duration::from_secs(6 * 3600);
 
 const SECONDS_PER_DAY: i64 = 86_400;
 
@@ -94,14 +122,29 @@ pub struct Accountant {
     consuming_wallet: Option<Wallet>,
     earning_wallet: Wallet,
     payable_dao: Box<dyn PayableDao>,
+    payment_journal: Box<dyn PaymentJournal>,
     receivable_dao: Box<dyn ReceivableDao>,
     banned_dao: Box<dyn BannedDao>,
+    usage_history_dao: Box<dyn UsageHistoryDao>,
+    trust_dao: Box<dyn TrustDao>,
+    financials_dao: Box<dyn FinancialsDao>,
+    usage_recorded: (SystemTime, UsageCounters),
+    data_cap_config_opt: Option<DataCapConfig>,
+    data_cap_opt: Option<DataCapTracker>,
     persistent_configuration: Box<dyn PersistentConfiguration>,
     report_accounts_payable_sub: Option<Recipient<ReportAccountsPayable>>,
     retrieve_transactions_sub: Option<Recipient<RetrieveTransactions>>,
     report_new_payments_sub: Option<Recipient<ReceivedPayments>>,
     report_sent_payments_sub: Option<Recipient<SentPayments>>,
     ui_message_sub: Option<Recipient<NodeToUiMessage>>,
+    payment_overdue_sub: Option<Recipient<PaymentOverdue_0v1>>,
+    // Delinquent wallets that have been sent a payment-overdue notice but not banned yet, and when
+    dunned: HashMap<Wallet, SystemTime>,
+    clock: Arc<dyn Clock>,
+    // A local proxy neither pays nor is paid, so there's nothing to scan for
+    local_proxy: bool,
+    // Whether other Nodes' attestations count for wallets this Node has no payment record for
+    heed_attestations: bool,
     logger: Logger,
 }
 
@@ -109,6 +152,26 @@ impl Actor for Accountant {
     type Context = Context<Self>;
 }
 
+// Running totals since the Node started, as of some moment; usage is the difference between two
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct UsageCounters {
+    routed_bytes: u64,
+    earned_gwei: u64,
+    neighbors_gained: u64,
+    neighbors_lost: u64,
+}
+
+impl UsageCounters {
+    fn read() -> Self {
+        Self {
+            routed_bytes: metrics::ROUTED_BYTES.get() + metrics::EXIT_BYTES.get(),
+            earned_gwei: metrics::EARNED_GWEI.get(),
+            neighbors_gained: metrics::NEIGHBORS_GAINED.get(),
+            neighbors_lost: metrics::NEIGHBORS_LOST.get(),
+        }
+    }
+}
+
 #[derive(Debug, Eq, Message, PartialEq)]
 pub struct ReceivedPayments {
     payments: Vec<Transaction>,
@@ -134,15 +197,29 @@ impl Handler<StartMessage> for Accountant {
     fn handle(&mut self, _msg: StartMessage, ctx: &mut Self::Context) -> Self::Result {
         self.handle_start_message();
 
-        ctx.run_interval(self.config.payable_scan_interval, |accountant, _ctx| {
-            accountant.scan_for_payables();
-        });
+        if !self.local_proxy {
+            ctx.run_interval(
+                self.clock.real_interval(self.config.payable_scan_interval),
+                |accountant, _ctx| {
+                    accountant.scan_for_payables();
+                },
+            );
+
+            ctx.run_interval(
+                self.clock
+                    .real_interval(self.config.payment_received_scan_interval),
+                |accountant, _ctx| {
+                    accountant.scan_for_received_payments();
+                    accountant.scan_for_delinquencies();
+                },
+            );
+        }
 
         ctx.run_interval(
-            self.config.payment_received_scan_interval,
+            self.clock.real_interval(USAGE_RECORDING_INTERVAL),
             |accountant, _ctx| {
-                accountant.scan_for_received_payments();
-                accountant.scan_for_delinquencies();
+                accountant.record_usage(accountant.clock.now(), UsageCounters::read());
+                accountant.roll_over_data_cap(Local::now());
             },
         );
     }
@@ -212,6 +289,30 @@ impl Handler<ReportExitServiceConsumedMessage> for Accountant {
     }
 }
 
+impl Handler<ReportEarningWalletChangeMessage> for Accountant {
+    type Result = ();
+
+    fn handle(
+        &mut self,
+        msg: ReportEarningWalletChangeMessage,
+        _ctx: &mut Self::Context,
+    ) -> Self::Result {
+        self.handle_report_earning_wallet_change_message(msg);
+    }
+}
+
+impl Handler<ExpiredCoresPackage<PaymentOverdue_0v1>> for Accountant {
+    type Result = ();
+
+    fn handle(
+        &mut self,
+        msg: ExpiredCoresPackage<PaymentOverdue_0v1>,
+        _ctx: &mut Self::Context,
+    ) -> Self::Result {
+        self.handle_payment_overdue(msg.payload);
+    }
+}
+
 impl Handler<NodeFromUiMessage> for Accountant {
     type Result = ();
 
@@ -221,11 +322,15 @@ impl Handler<NodeFromUiMessage> for Accountant {
 }
 
 impl Accountant {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         config: &BootstrapperConfig,
         payable_dao_factory: Box<dyn PayableDaoFactory>,
         receivable_dao_factory: Box<dyn ReceivableDaoFactory>,
         banned_dao_factory: Box<dyn BannedDaoFactory>,
+        usage_history_dao_factory: Box<dyn UsageHistoryDaoFactory>,
+        trust_dao_factory: Box<dyn TrustDaoFactory>,
+        financials_dao_factory: Box<dyn FinancialsDaoFactory>,
         config_dao_factory: Box<dyn ConfigDaoFactory>,
     ) -> Accountant {
         Accountant {
@@ -233,8 +338,15 @@ impl Accountant {
             consuming_wallet: config.consuming_wallet.clone(),
             earning_wallet: config.earning_wallet.clone(),
             payable_dao: payable_dao_factory.make(),
+            payment_journal: Box::new(PaymentJournalReal::new(&config.data_directory)),
             receivable_dao: receivable_dao_factory.make(),
             banned_dao: banned_dao_factory.make(),
+            usage_history_dao: usage_history_dao_factory.make(),
+            trust_dao: trust_dao_factory.make(),
+            financials_dao: financials_dao_factory.make(),
+            usage_recorded: (SystemTime::now(), UsageCounters::read()),
+            data_cap_config_opt: config.data_cap_opt.clone(),
+            data_cap_opt: None,
             persistent_configuration: Box::new(PersistentConfigurationReal::new(
                 config_dao_factory.make(),
             )),
@@ -243,10 +355,22 @@ impl Accountant {
             report_new_payments_sub: None,
             report_sent_payments_sub: None,
             ui_message_sub: None,
+            payment_overdue_sub: None,
+            dunned: HashMap::new(),
+            clock: Arc::new(ClockReal {}),
+            local_proxy: config.local_proxy,
+            heed_attestations: config.heed_attestations,
             logger: Logger::new("Accountant"),
         }
     }
 
+    // For harnesses that play out weeks of payments and bans on a VirtualClock
+    #[cfg(any(test, feature = "virtual_time"))]
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.usage_recorded.0 = clock.now();
+        self.clock = clock;
+    }
+
     pub fn make_subs_from(addr: &Addr<Accountant>) -> AccountantSubs {
         AccountantSubs {
             bind: addr.clone().recipient::<BindMessage>(),
@@ -265,6 +389,12 @@ impl Accountant {
                 .recipient::<ReportExitServiceConsumedMessage>(),
             report_new_payments: addr.clone().recipient::<ReceivedPayments>(),
             report_sent_payments: addr.clone().recipient::<SentPayments>(),
+            report_earning_wallet_change: addr
+                .clone()
+                .recipient::<ReportEarningWalletChangeMessage>(),
+            payment_overdue_from_hopper: addr
+                .clone()
+                .recipient::<ExpiredCoresPackage<PaymentOverdue_0v1>>(),
             ui_message_sub: addr.clone().recipient::<NodeFromUiMessage>(),
         }
     }
@@ -273,14 +403,26 @@ impl Accountant {
         debug!(self.logger, "Scanning for payables");
         let future_logger = self.logger.clone();
 
-        let payables = self
-            .payable_dao
-            .non_pending_payables()
+        let non_pending_payables = self.payable_dao.non_pending_payables();
+        metrics::PAYABLES_GWEI.set(
+            non_pending_payables
+                .iter()
+                .map(|account| account.balance.max(0) as u64)
+                .sum(),
+        );
+        let now = self.clock.now();
+        let payables = non_pending_payables
             .into_iter()
-            .filter(Accountant::should_pay)
+            .filter(|payable| Accountant::should_pay(payable, now))
             .collect::<Vec<PayableAccount>>();
 
-        if !payables.is_empty() {
+        if !payables.is_empty() && self.consuming_wallet.is_none() {
+            debug!(
+                self.logger,
+                "{} payable(s) due, but this Node is earning-only and has no consuming wallet to pay from",
+                payables.len()
+            );
+        } else if !payables.is_empty() {
             let report_sent_payments = self.report_sent_payments_sub.clone();
             let future = self
                 .report_accounts_payable_sub
@@ -312,35 +454,63 @@ impl Accountant {
         }
     }
 
+    // A newly delinquent wallet is sent a payment-overdue notice first, and banned only if it's
+    // still delinquent a dunning period later: DUNNING_PERIOD for a wallet of neutral trust
     fn scan_for_delinquencies(&mut self) {
         debug!(self.logger, "Scanning for delinquencies");
 
-        let now = SystemTime::now();
-        self.receivable_dao
-            .new_delinquencies(now, &PAYMENT_CURVES)
-            .into_iter()
-            .for_each(|account| {
-                self.banned_dao.ban(&account.wallet);
-                let (balance, age) = Self::balance_and_age(&account);
-                info!(
-                    self.logger,
-                    "Wallet {} (balance: {} MASQ, age: {} sec) banned for delinquency",
-                    account.wallet,
-                    balance,
-                    age.as_secs()
-                )
-            });
+        let now = self.clock.now();
+        let delinquencies = self.receivable_dao.new_delinquencies(now, &PAYMENT_CURVES);
+        self.dunned.retain(|wallet, _| {
+            delinquencies
+                .iter()
+                .any(|account| &account.wallet == wallet)
+        });
+        for account in delinquencies {
+            let (balance, age) = self.balance_and_age(&account);
+            match self.dunned.get(&account.wallet).cloned() {
+                None => {
+                    self.dun(&account, age);
+                    self.dunned.insert(account.wallet.clone(), now);
+                    info!(
+                        self.logger,
+                        "Wallet {} (balance: {} MASQ, age: {} sec) is delinquent: sent a payment-overdue notice; banning it in {} sec unless it pays",
+                        Redacted(&account.wallet),
+                        balance,
+                        age.as_secs(),
+                        Self::dunning_period(&account.wallet, self.heed_attestations).as_secs()
+                    )
+                }
+                Some(dunned_at)
+                    if self.clock.elapsed_since(dunned_at)
+                        >= Self::dunning_period(&account.wallet, self.heed_attestations) =>
+                {
+                    self.dunned.remove(&account.wallet);
+                    self.banned_dao.ban(&account.wallet);
+                    self.trust_dao
+                        .add_evidence(&account.wallet, TrustEvidence::PaymentOverdue);
+                    info!(
+                        self.logger,
+                        "Wallet {} (balance: {} MASQ, age: {} sec) banned for delinquency",
+                        Redacted(&account.wallet),
+                        balance,
+                        age.as_secs()
+                    )
+                }
+                Some(_) => (),
+            }
+        }
 
         self.receivable_dao
             .paid_delinquencies(&PAYMENT_CURVES)
             .into_iter()
             .for_each(|account| {
                 self.banned_dao.unban(&account.wallet);
-                let (balance, age) = Self::balance_and_age(&account);
+                let (balance, age) = self.balance_and_age(&account);
                 info!(
                     self.logger,
                     "Wallet {} (balance: {} MASQ, age: {} sec) is no longer delinquent: unbanned",
-                    account.wallet,
+                    Redacted(&account.wallet),
                     balance,
                     age.as_secs()
                 )
@@ -351,7 +521,8 @@ impl Accountant {
         let future_logger = self.logger.clone();
         debug!(
             self.logger,
-            "Scanning for payments to {}", self.earning_wallet
+            "Scanning for payments to {}",
+            Redacted(&self.earning_wallet)
         );
         let future_report_new_payments_sub = self.report_new_payments_sub.clone();
         let start_block = match self.persistent_configuration.start_block() {
@@ -415,20 +586,47 @@ impl Accountant {
         actix::spawn(future);
     }
 
-    fn balance_and_age(account: &ReceivableAccount) -> (String, Duration) {
+    // The notice can reach the debtor only over a stream it's paying this Node to exit
+    fn dun(&self, account: &ReceivableAccount, age: Duration) {
+        if let Some(payment_overdue_sub) = self.payment_overdue_sub.as_ref() {
+            payment_overdue_sub
+                .try_send(PaymentOverdue_0v1 {
+                    creditor: self.earning_wallet.clone(),
+                    debtor: account.wallet.clone(),
+                    balance: account.balance,
+                    age_sec: age.as_secs(),
+                    ban_in_sec: Self::dunning_period(&account.wallet, self.heed_attestations)
+                        .as_secs(),
+                })
+                .expect("ProxyClient is dead");
+        }
+    }
+
+    // How much unpaid service a delinquent wallet gets after its notice: from a quarter of
+    // DUNNING_PERIOD for the least trusted wallets to one and three quarters for the most
+    fn dunning_period(wallet: &Wallet, heed_attestations: bool) -> Duration {
+        let score = if heed_attestations {
+            score_with_attestations(wallet)
+        } else {
+            TRUST_CACHE.score(wallet)
+        };
+        let leeway = 0.25 + 1.5 * (score as f64 / 100.0);
+        Duration::from_secs((DUNNING_PERIOD.as_secs() as f64 * leeway).round() as u64)
+    }
+
+    fn balance_and_age(&self, account: &ReceivableAccount) -> (String, Duration) {
         let balance = format!("{}", (account.balance as f64) / 1_000_000_000.0);
-        let age = account
-            .last_received_timestamp
-            .elapsed()
-            .unwrap_or_else(|_| Duration::new(0, 0));
+        let age = self.clock.elapsed_since(account.last_received_timestamp);
         (balance, age)
     }
 
-    fn should_pay(payable: &PayableAccount) -> bool {
+    fn should_pay(payable: &PayableAccount, now: SystemTime) -> bool {
         // TODO: This calculation should be done in the database, if possible
-        let time_since_last_paid = SystemTime::now()
+        // A payment in this clock's future means the clock has been set back since; until it
+        // catches up, the payment is as recent as a payment can be
+        let time_since_last_paid = now
             .duration_since(payable.last_paid_timestamp)
-            .expect("Internal error")
+            .unwrap_or_else(|_| Duration::new(0, 0))
             .as_secs();
 
         if time_since_last_paid <= PAYMENT_CURVES.payment_suggested_after_sec as u64 {
@@ -466,11 +664,14 @@ impl Accountant {
             match self.receivable_dao
                 .as_ref()
                 .more_money_receivable(wallet, total_charge) {
-                Ok(_) => (),
+                Ok(_) => {
+                    metrics::EARNED_GWEI.add(total_charge);
+                    EARNINGS_CACHE.add_charge(wallet, total_charge)
+                }
                 Err(PaymentError::SignConversion(_)) => error! (
                     self.logger,
                     "Overflow error trying to record service provided to Node with consuming wallet {}: service rate {}, byte rate {}, payload size {}. Skipping",
-                    wallet,
+                    Redacted(wallet),
                     service_rate,
                     byte_rate,
                     payload_size
@@ -479,7 +680,8 @@ impl Accountant {
         } else {
             info!(
                 self.logger,
-                "Not recording service provided for our wallet {}", wallet
+                "Not recording service provided for our wallet {}",
+                Redacted(wallet)
             );
         }
     }
@@ -501,7 +703,7 @@ impl Accountant {
                 Err(PaymentError::SignConversion(_)) => error! (
                     self.logger,
                     "Overflow error trying to record service consumed from Node with earning wallet {}: service rate {}, byte rate {}, payload size {}. Skipping",
-                    wallet,
+                    Redacted(wallet),
                     service_rate,
                     byte_rate,
                     payload_size
@@ -510,11 +712,92 @@ impl Accountant {
         } else {
             info!(
                 self.logger,
-                "Not recording service consumed to our wallet {}", wallet
+                "Not recording service consumed to our wallet {}",
+                Redacted(wallet)
             );
         }
     }
 
+    // Whatever happened since the last recording is charged to the day it's recorded on
+    fn record_usage(&mut self, now: SystemTime, counters: UsageCounters) {
+        let (then, counters_then) = self.usage_recorded;
+        self.usage_history_dao.add_usage(&DailyUsage {
+            day: DateTime::<Utc>::from(now).format("%Y-%m-%d").to_string(),
+            uptime_seconds: now
+                .duration_since(then)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or(0),
+            routed_bytes: counters
+                .routed_bytes
+                .saturating_sub(counters_then.routed_bytes),
+            earned_gwei: counters
+                .earned_gwei
+                .saturating_sub(counters_then.earned_gwei),
+            neighbors_gained: counters
+                .neighbors_gained
+                .saturating_sub(counters_then.neighbors_gained),
+            neighbors_lost: counters
+                .neighbors_lost
+                .saturating_sub(counters_then.neighbors_lost),
+        });
+        self.usage_recorded = (now, counters);
+    }
+
+    // Usage recorded before the Node started counts in full, whatever time of day it went by
+    fn start_data_cap(&mut self, now: DateTime<Local>) {
+        let config = match &self.data_cap_config_opt {
+            Some(config) => config.clone(),
+            None => return,
+        };
+        let day_prefix = format!("{}-", DataCapTracker::month_of(now));
+        let used_bytes = self
+            .usage_history_dao
+            .history(31)
+            .into_iter()
+            .filter(|usage| usage.day.starts_with(&day_prefix))
+            .map(|usage| usage.routed_bytes)
+            .sum();
+        let tracker = DataCapTracker::new(config, now, used_bytes);
+        self.update_data_cap_status(tracker.status());
+        self.data_cap_opt = Some(tracker);
+    }
+
+    fn charge_data_cap(&mut self, bytes: u64, now: DateTime<Local>) {
+        if let Some(tracker) = self.data_cap_opt.as_mut() {
+            let status = tracker.record(bytes, now);
+            self.update_data_cap_status(status);
+        }
+    }
+
+    fn roll_over_data_cap(&mut self, now: DateTime<Local>) {
+        if let Some(tracker) = self.data_cap_opt.as_mut() {
+            let status = tracker.roll_over(now);
+            self.update_data_cap_status(status);
+        }
+    }
+
+    fn update_data_cap_status(&self, status: DataCapStatus) {
+        if status == data_cap::status() {
+            return;
+        }
+        data_cap::set_status(status);
+        match status {
+            DataCapStatus::Open => info!(
+                self.logger,
+                "Data cap has room again; serving other Nodes as usual"
+            ),
+            DataCapStatus::Throttled => warning!(
+                self.logger,
+                "{}% of this month's data cap is used; taking on no new exit streams",
+                THROTTLE_PERCENT
+            ),
+            DataCapStatus::Exhausted => warning!(
+                self.logger,
+                "This month's data cap is used up; neither routing nor exiting for other Nodes until next month"
+            ),
+        }
+    }
+
     fn our_wallet(&self, wallet: &Wallet) -> bool {
         match &self.consuming_wallet {
             Some(ref consuming) if consuming.address() == wallet.address() => true,
@@ -530,17 +813,104 @@ impl Accountant {
         self.report_new_payments_sub = Some(msg.peer_actors.accountant.report_new_payments);
         self.report_sent_payments_sub = Some(msg.peer_actors.accountant.report_sent_payments);
         self.ui_message_sub = Some(msg.peer_actors.ui_gateway.node_to_ui_message_sub);
+        self.payment_overdue_sub = Some(msg.peer_actors.proxy_client.payment_overdue);
 
         info!(self.logger, "Accountant bound");
     }
 
+    // Payments can be reported before the UiGateway is bound; nobody can have subscribed yet then
+    fn broadcast_payment<B: ToMessageBody>(&self, broadcast: B) {
+        if let Some(ui_message_sub) = self.ui_message_sub.as_ref() {
+            ui_message_sub
+                .try_send(NodeToUiMessage {
+                    target: AllClients,
+                    body: broadcast.tmb(0),
+                })
+                .expect("UiGateway is dead");
+        }
+    }
+
     fn handle_start_message(&mut self) {
+        self.start_data_cap(Local::now());
+        if self.local_proxy {
+            info!(
+                self.logger,
+                "Running as a local proxy: not scanning for payments"
+            );
+            return;
+        }
+        self.recover_payments();
         self.scan_for_payables();
         self.scan_for_received_payments();
         self.scan_for_delinquencies();
     }
 
+    // Payments the BlockchainBridge sent but the Accountant never heard about before the Node went
+    // down; unless the payable already shows them, they're recorded now, before anybody is paid again
+    fn recover_payments(&mut self) {
+        self.payment_journal
+            .unsettled()
+            .into_iter()
+            .for_each(|payment| {
+                let already_recorded = match self.payable_dao.account_status(&payment.to) {
+                    Some(account) => {
+                        account.pending_payment_transaction == Some(payment.transaction)
+                    }
+                    None => false,
+                };
+                if !already_recorded {
+                    warning!(
+                        self.logger,
+                        "Recovering payment of {} sent to {} (transaction {}) before the Node went down",
+                        payment.amount,
+                        payment.to,
+                        payment.transaction
+                    );
+                    if let Err(e) = self.payable_dao.as_mut().payment_sent(&payment) {
+                        error!(
+                            self.logger,
+                            "Could not record recovered payment of {} sent to {} (transaction {}): {:?}",
+                            payment.amount,
+                            payment.to,
+                            payment.transaction,
+                            e
+                        )
+                    }
+                }
+                self.payment_journal.record_settled(&payment)
+            })
+    }
+
     fn handle_received_payments(&mut self, received_payments: ReceivedPayments) {
+        received_payments.payments.iter().for_each(|transaction| {
+            info!(
+                self.logger,
+                "Received {} Gwei from {} (transaction 0x{:0X}, {} confirmations)",
+                transaction.gwei_amount,
+                transaction.from,
+                transaction.transaction_hash,
+                transaction.confirmations
+            );
+            self.broadcast_payment(UiPaymentBroadcast {
+                direction: "received".to_string(),
+                wallet: transaction.from.to_string(),
+                amount: transaction.gwei_amount,
+                transaction_opt: None,
+            });
+            self.broadcast_payment(UiPaymentReceivedBroadcast {
+                payer: transaction.from.to_string(),
+                amount: transaction.gwei_amount,
+                transaction: format!("0x{:0X}", transaction.transaction_hash),
+                confirmations: transaction.confirmations,
+            });
+            EARNINGS_CACHE.add_payment(&transaction.from, transaction.gwei_amount);
+            if !self.dunned.contains_key(&transaction.from)
+                && !BAN_CACHE.is_banned(&transaction.from)
+            {
+                self.trust_dao
+                    .add_evidence(&transaction.from, TrustEvidence::PaidOnTime);
+            }
+        });
         self.receivable_dao
             .as_mut()
             .more_money_received(received_payments.payments);
@@ -551,16 +921,24 @@ impl Accountant {
             .payments
             .iter()
             .for_each(|payment| match payment {
-                Ok(payment) => match self.payable_dao.as_mut().payment_sent(payment) {
-                    Ok(()) => (),
-                    Err(PaymentError::SignConversion(_)) => error! (
-                        self.logger,
-                        "Overflow error trying to record payment of {} sent to earning wallet {} (transaction {}). Skipping",
-                        payment.amount,
-                        payment.to,
-                        payment.transaction,
-                    ),
-                },
+                Ok(payment) => {
+                    match self.payable_dao.as_mut().payment_sent(payment) {
+                        Ok(()) => self.broadcast_payment(UiPaymentBroadcast {
+                            direction: "sent".to_string(),
+                            wallet: payment.to.to_string(),
+                            amount: payment.amount,
+                            transaction_opt: Some(format!("0x{:0X}", payment.transaction)),
+                        }),
+                        Err(PaymentError::SignConversion(_)) => error! (
+                            self.logger,
+                            "Overflow error trying to record payment of {} sent to earning wallet {} (transaction {}). Skipping",
+                            payment.amount,
+                            payment.to,
+                            payment.transaction,
+                        ),
+                    }
+                    self.payment_journal.record_settled(payment)
+                }
                 Err(e) => warning!(
                     self.logger,
                     "{} Please check your blockchain service URL configuration.",
@@ -575,8 +953,12 @@ impl Accountant {
     ) {
         debug!(
             self.logger,
-            "Charging routing of {} bytes to wallet {}", msg.payload_size, msg.paying_wallet
+            "Charging routing of {} bytes to wallet {}",
+            msg.payload_size,
+            Redacted(&msg.paying_wallet)
         );
+        metrics::ROUTED_BYTES.add(msg.payload_size as u64);
+        self.charge_data_cap(msg.payload_size as u64, Local::now());
         self.record_service_provided(
             msg.service_rate,
             msg.byte_rate,
@@ -593,10 +975,12 @@ impl Accountant {
             self.logger,
             "Charging exit service for {} bytes to wallet {} at {} per service and {} per byte",
             msg.payload_size,
-            msg.paying_wallet,
+            Redacted(&msg.paying_wallet),
             msg.service_rate,
             msg.byte_rate
         );
+        metrics::EXIT_BYTES.add(msg.payload_size as u64);
+        self.charge_data_cap(msg.payload_size as u64, Local::now());
         self.record_service_provided(
             msg.service_rate,
             msg.byte_rate,
@@ -612,7 +996,7 @@ impl Accountant {
         debug!(
             self.logger,
             "Accruing debt to wallet {} for consuming routing service {} bytes",
-            msg.earning_wallet,
+            Redacted(&msg.earning_wallet),
             msg.payload_size
         );
         self.record_service_consumed(
@@ -630,7 +1014,7 @@ impl Accountant {
         debug!(
             self.logger,
             "Accruing debt to wallet {} for consuming exit service {} bytes",
-            msg.earning_wallet,
+            Redacted(&msg.earning_wallet),
             msg.payload_size
         );
         self.record_service_consumed(
@@ -641,8 +1025,75 @@ impl Accountant {
         );
     }
 
+    // What's already owed to the previous wallet is owed to the new one; a payment still pending
+    // to the previous wallet keeps that account where it is until a later announcement moves it
+    fn handle_report_earning_wallet_change_message(
+        &mut self,
+        msg: ReportEarningWalletChangeMessage,
+    ) {
+        match self
+            .payable_dao
+            .transfer_balance(&msg.previous_wallet, &msg.current_wallet)
+        {
+            Some(balance) => info!(
+                self.logger,
+                "Moved debt of {} from wallet {} to wallet {}, which replaces it as of block {}",
+                balance,
+                Redacted(&msg.previous_wallet),
+                Redacted(&msg.current_wallet),
+                msg.effective_block
+            ),
+            None => debug!(
+                self.logger,
+                "No debt to move from wallet {} to wallet {}",
+                Redacted(&msg.previous_wallet),
+                Redacted(&msg.current_wallet)
+            ),
+        }
+    }
+
+    // Any Node can claim to be owed; whether it's right is for the operator to judge
+    fn handle_payment_overdue(&self, notice: PaymentOverdue_0v1) {
+        let balance = (notice.balance as f64) / 1_000_000_000.0;
+        match self.consuming_wallet.as_ref() {
+            Some(wallet) if wallet.address() == notice.debtor.address() => (),
+            _ => {
+                warning!(
+                    self.logger,
+                    "Ignoring notice from wallet {} that wallet {} owes it {} MASQ: that isn't this Node's consuming wallet",
+                    Redacted(&notice.creditor),
+                    Redacted(&notice.debtor),
+                    balance
+                );
+                return;
+            }
+        }
+        warning!(
+            self.logger,
+            "Wallet {} says this Node's consuming wallet {} has owed it {} MASQ for {} sec, and will stop serving this Node in {} sec unless it's paid",
+            Redacted(&notice.creditor),
+            Redacted(&notice.debtor),
+            balance,
+            notice.age_sec,
+            notice.ban_in_sec
+        );
+        self.broadcast_payment(UiPaymentOverdueBroadcast {
+            creditor: notice.creditor.to_string(),
+            debtor: notice.debtor.to_string(),
+            balance: notice.balance,
+            age_seconds: notice.age_sec,
+            ban_in_seconds: notice.ban_in_sec,
+        });
+    }
+
     fn handle_node_from_ui_message(&mut self, msg: NodeFromUiMessage) {
         let client_id = msg.client_id;
+        if let Ok((payload, context_id)) = UiUsageHistoryRequest::fmb(msg.body.clone()) {
+            return self.handle_usage_history(client_id, context_id, payload);
+        }
+        if let Ok((_, context_id)) = UiDataCapRequest::fmb(msg.body.clone()) {
+            return self.handle_data_cap(client_id, context_id);
+        }
         let result: Result<(UiFinancialsRequest, u64), UiMessageError> =
             UiFinancialsRequest::fmb(msg.body);
         match result {
@@ -655,16 +1106,53 @@ impl Accountant {
         }
     }
 
+    fn handle_data_cap(&mut self, client_id: u64, context_id: u64) {
+        let now = Local::now();
+        self.roll_over_data_cap(now);
+        let response = match &self.data_cap_opt {
+            Some(tracker) => UiDataCapResponse {
+                month: tracker.month().to_string(),
+                monthly_bytes_opt: Some(tracker.monthly_bytes()),
+                used_bytes_opt: Some(tracker.used_bytes()),
+                remaining_bytes_opt: Some(tracker.remaining_bytes()),
+                status: tracker.status().name().to_string(),
+            },
+            None => UiDataCapResponse {
+                month: DataCapTracker::month_of(now),
+                monthly_bytes_opt: None,
+                used_bytes_opt: None,
+                remaining_bytes_opt: None,
+                status: DataCapStatus::Open.name().to_string(),
+            },
+        };
+        self.ui_message_sub
+            .as_ref()
+            .expect("UiGateway not bound")
+            .try_send(NodeToUiMessage {
+                target: ClientId(client_id),
+                body: response.tmb(context_id),
+            })
+            .expect("UiGateway is dead");
+    }
+
+    // From a snapshot, so that a payable settled while the response was being put together doesn't
+    // show up as still owing, and the totals are of the same moment's accounts
     fn handle_financials(&mut self, client_id: u64, context_id: u64, request: UiFinancialsRequest) {
-        let payables = self
-            .payable_dao
-            .top_records(request.payable_minimum_amount, request.payable_maximum_age)
+        let snapshot = self.financials_dao.snapshot(
+            self.clock.now(),
+            request.payable_minimum_amount,
+            request.payable_maximum_age,
+            request.receivable_minimum_amount,
+            request.receivable_maximum_age,
+        );
+        let payables = snapshot
+            .payables
             .iter()
             .map(|account| UiPayableAccount {
                 wallet: account.wallet.to_string(),
-                age: SystemTime::now()
-                    .duration_since(account.last_paid_timestamp)
-                    .expect("Bad interval")
+                age: self
+                    .clock
+                    .elapsed_since(account.last_paid_timestamp)
                     .as_secs(),
                 amount: account.balance as u64,
                 pending_transaction: account
@@ -672,24 +1160,20 @@ impl Accountant {
                     .map(|ppt| format!("0x{:0X}", ppt)),
             })
             .collect_vec();
-        let total_payable = self.payable_dao.total();
-        let receivables = self
-            .receivable_dao
-            .top_records(
-                request.receivable_minimum_amount,
-                request.receivable_maximum_age,
-            )
+        let receivables = snapshot
+            .receivables
             .iter()
             .map(|account| UiReceivableAccount {
                 wallet: account.wallet.to_string(),
-                age: SystemTime::now()
-                    .duration_since(account.last_received_timestamp)
-                    .expect("Bad interval")
+                age: self
+                    .clock
+                    .elapsed_since(account.last_received_timestamp)
                     .as_secs(),
                 amount: account.balance as u64,
             })
             .collect_vec();
-        let total_receivable = self.receivable_dao.total();
+        let total_payable = snapshot.total_payable;
+        let total_receivable = snapshot.total_receivable;
         let body = UiFinancialsResponse {
             payables,
             total_payable,
@@ -706,6 +1190,37 @@ impl Accountant {
             })
             .expect("UiGateway is dead");
     }
+
+    // Records what's happened so far today first, so the answer is up to the minute
+    fn handle_usage_history(
+        &mut self,
+        client_id: u64,
+        context_id: u64,
+        request: UiUsageHistoryRequest,
+    ) {
+        self.record_usage(self.clock.now(), UsageCounters::read());
+        let days = self
+            .usage_history_dao
+            .history(request.days)
+            .into_iter()
+            .map(|usage| UiUsageDay {
+                date: usage.day,
+                uptime_seconds: usage.uptime_seconds,
+                routed_bytes: usage.routed_bytes,
+                earned_gwei: usage.earned_gwei,
+                neighbors_gained: usage.neighbors_gained,
+                neighbors_lost: usage.neighbors_lost,
+            })
+            .collect_vec();
+        self.ui_message_sub
+            .as_ref()
+            .expect("UiGateway not bound")
+            .try_send(NodeToUiMessage {
+                target: ClientId(client_id),
+                body: UiUsageHistoryResponse { days }.tmb(context_id),
+            })
+            .expect("UiGateway is dead");
+    }
 }
 
 // At the time of this writing, Rust 1.44.0 was unpredictably producing
@@ -722,8 +1237,10 @@ pub fn jackass_unsigned_to_signed(unsigned: u64) -> Result<i64, PaymentError> {
 #[cfg(test)]
 pub mod tests {
     use super::*;
+    use crate::accountant::financials_dao::FinancialsSnapshot;
+    use crate::accountant::neighbor_earnings::Earnings;
     use crate::accountant::receivable_dao::{ReceivableAccount, ReceivableDaoFactory};
-    use crate::accountant::test_utils::make_receivable_account;
+    use crate::accountant::test_utils::{make_receivable_account, PaymentJournalMock};
     use crate::blockchain::blockchain_interface::BlockchainError;
     use crate::blockchain::blockchain_interface::Transaction;
     use crate::database::dao_utils::from_time_t;
@@ -732,27 +1249,38 @@ pub mod tests {
     use crate::db_config::mocks::ConfigDaoMock;
     use crate::db_config::persistent_configuration::PersistentConfigError;
     use crate::sub_lib::accountant::ReportRoutingServiceConsumedMessage;
+    use crate::sub_lib::attestation::{Attestation, ATTESTATION_CACHE};
     use crate::sub_lib::blockchain_bridge::ReportAccountsPayable;
+    use crate::sub_lib::redacted;
+    use crate::sub_lib::route::Route;
     use crate::sub_lib::wallet::Wallet;
     use crate::test_utils::logging::init_test_logging;
     use crate::test_utils::logging::TestLogHandler;
+    use crate::test_utils::main_cryptde;
+    use crate::test_utils::make_paying_wallet;
     use crate::test_utils::make_wallet;
     use crate::test_utils::persistent_configuration_mock::PersistentConfigurationMock;
     use crate::test_utils::recorder::make_recorder;
     use crate::test_utils::recorder::peer_actors_builder;
     use crate::test_utils::recorder::Recorder;
+    use crate::test_utils::virtual_clock::VirtualClock;
+    use crate::trust_dao::TrustRecord;
     use actix::System;
+    use chrono::TimeZone;
     use ethereum_types::BigEndianHash;
     use ethsign_crypto::Keccak256;
     use masq_lib::ui_gateway::MessagePath::{Conversation, FireAndForget};
     use masq_lib::ui_gateway::{MessageBody, MessageTarget, NodeFromUiMessage, NodeToUiMessage};
     use std::cell::RefCell;
     use std::convert::TryFrom;
+    use std::net::SocketAddr;
     use std::ops::Sub;
+    use std::str::FromStr;
     use std::sync::Mutex;
     use std::sync::{Arc, MutexGuard};
     use std::thread;
     use std::time::Duration;
+    use std::time::Instant;
     use std::time::SystemTime;
     use web3::types::H256;
     use web3::types::U256;
@@ -766,9 +1294,8 @@ pub mod tests {
         non_pending_payables_results: RefCell<Vec<Vec<PayableAccount>>>,
         payment_sent_parameters: Arc<Mutex<Vec<Payment>>>,
         payment_sent_results: RefCell<Vec<Result<(), PaymentError>>>,
-        top_records_parameters: Arc<Mutex<Vec<(u64, u64)>>>,
-        top_records_results: RefCell<Vec<Vec<PayableAccount>>>,
-        total_results: RefCell<Vec<u64>>,
+        transfer_balance_parameters: Arc<Mutex<Vec<(Wallet, Wallet)>>>,
+        transfer_balance_results: RefCell<Vec<Option<i64>>>,
     }
 
     impl PayableDao for PayableDaoMock {
@@ -814,16 +1341,12 @@ pub mod tests {
             }
         }
 
-        fn top_records(&self, minimum_amount: u64, maximum_age: u64) -> Vec<PayableAccount> {
-            self.top_records_parameters
+        fn transfer_balance(&self, from: &Wallet, to: &Wallet) -> Option<i64> {
+            self.transfer_balance_parameters
                 .lock()
                 .unwrap()
-                .push((minimum_amount, maximum_age));
-            self.top_records_results.borrow_mut().remove(0)
-        }
-
-        fn total(&self) -> u64 {
-            self.total_results.borrow_mut().remove(0)
+                .push((from.clone(), to.clone()));
+            self.transfer_balance_results.borrow_mut().remove(0)
         }
     }
 
@@ -832,6 +1355,16 @@ pub mod tests {
             PayableDaoMock::default()
         }
 
+        fn account_status_parameters(mut self, parameters: &Arc<Mutex<Vec<Wallet>>>) -> Self {
+            self.account_status_parameters = parameters.clone();
+            self
+        }
+
+        fn account_status_result(self, result: Option<PayableAccount>) -> Self {
+            self.account_status_results.borrow_mut().push(result);
+            self
+        }
+
         fn more_money_payable_parameters(
             mut self,
             parameters: Arc<Mutex<Vec<(Wallet, u64)>>>,
@@ -860,18 +1393,16 @@ pub mod tests {
             self
         }
 
-        fn top_records_parameters(mut self, parameters: &Arc<Mutex<Vec<(u64, u64)>>>) -> Self {
-            self.top_records_parameters = parameters.clone();
-            self
-        }
-
-        fn top_records_result(self, result: Vec<PayableAccount>) -> Self {
-            self.top_records_results.borrow_mut().push(result);
+        fn transfer_balance_parameters(
+            mut self,
+            parameters: &Arc<Mutex<Vec<(Wallet, Wallet)>>>,
+        ) -> Self {
+            self.transfer_balance_parameters = parameters.clone();
             self
         }
 
-        fn total_result(self, result: u64) -> Self {
-            self.total_results.borrow_mut().push(result);
+        fn transfer_balance_result(self, result: Option<i64>) -> Self {
+            self.transfer_balance_results.borrow_mut().push(result);
             self
         }
     }
@@ -907,9 +1438,6 @@ pub mod tests {
         new_delinquencies_results: RefCell<Vec<Vec<ReceivableAccount>>>,
         paid_delinquencies_parameters: Arc<Mutex<Vec<PaymentCurves>>>,
         paid_delinquencies_results: RefCell<Vec<Vec<ReceivableAccount>>>,
-        top_records_parameters: Arc<Mutex<Vec<(u64, u64)>>>,
-        top_records_results: RefCell<Vec<Vec<ReceivableAccount>>>,
-        total_results: RefCell<Vec<u64>>,
     }
 
     impl ReceivableDao for ReceivableDaoMock {
@@ -968,18 +1496,6 @@ pub mod tests {
                 self.paid_delinquencies_results.borrow_mut().remove(0)
             }
         }
-
-        fn top_records(&self, minimum_amount: u64, maximum_age: u64) -> Vec<ReceivableAccount> {
-            self.top_records_parameters
-                .lock()
-                .unwrap()
-                .push((minimum_amount, maximum_age));
-            self.top_records_results.borrow_mut().remove(0)
-        }
-
-        fn total(&self) -> u64 {
-            self.total_results.borrow_mut().remove(0)
-        }
     }
 
     impl ReceivableDaoMock {
@@ -1038,21 +1554,6 @@ pub mod tests {
             self.paid_delinquencies_results.borrow_mut().push(result);
             self
         }
-
-        fn top_records_parameters(mut self, parameters: &Arc<Mutex<Vec<(u64, u64)>>>) -> Self {
-            self.top_records_parameters = parameters.clone();
-            self
-        }
-
-        fn top_records_result(self, result: Vec<ReceivableAccount>) -> Self {
-            self.top_records_results.borrow_mut().push(result);
-            self
-        }
-
-        fn total_result(self, result: u64) -> Self {
-            self.total_results.borrow_mut().push(result);
-            self
-        }
     }
 
     pub struct ReceivableDaoFactoryMock {
@@ -1140,6 +1641,166 @@ pub mod tests {
         }
     }
 
+    #[derive(Default)]
+    pub struct UsageHistoryDaoMock {
+        add_usage_parameters: Arc<Mutex<Vec<DailyUsage>>>,
+        history_parameters: Arc<Mutex<Vec<u64>>>,
+        history_results: RefCell<Vec<Vec<DailyUsage>>>,
+    }
+
+    impl UsageHistoryDao for UsageHistoryDaoMock {
+        fn add_usage(&self, usage: &DailyUsage) {
+            self.add_usage_parameters
+                .lock()
+                .unwrap()
+                .push(usage.clone());
+        }
+
+        fn history(&self, days: u64) -> Vec<DailyUsage> {
+            self.history_parameters.lock().unwrap().push(days);
+            self.history_results.borrow_mut().remove(0)
+        }
+    }
+
+    impl UsageHistoryDaoMock {
+        fn add_usage_parameters(mut self, parameters: &Arc<Mutex<Vec<DailyUsage>>>) -> Self {
+            self.add_usage_parameters = parameters.clone();
+            self
+        }
+
+        fn history_parameters(mut self, parameters: &Arc<Mutex<Vec<u64>>>) -> Self {
+            self.history_parameters = parameters.clone();
+            self
+        }
+
+        fn history_result(self, result: Vec<DailyUsage>) -> Self {
+            self.history_results.borrow_mut().push(result);
+            self
+        }
+    }
+
+    pub struct UsageHistoryDaoFactoryMock {
+        mock: RefCell<Option<UsageHistoryDaoMock>>,
+    }
+
+    impl UsageHistoryDaoFactory for UsageHistoryDaoFactoryMock {
+        fn make(&self) -> Box<dyn UsageHistoryDao> {
+            Box::new(self.mock.borrow_mut().take().unwrap())
+        }
+    }
+
+    impl UsageHistoryDaoFactoryMock {
+        fn new(mock: UsageHistoryDaoMock) -> Self {
+            Self {
+                mock: RefCell::new(Some(mock)),
+            }
+        }
+    }
+
+    #[derive(Default)]
+    pub struct TrustDaoMock {
+        add_evidence_parameters: Arc<Mutex<Vec<(Wallet, TrustEvidence)>>>,
+    }
+
+    impl TrustDao for TrustDaoMock {
+        fn trust_list(&self) -> Vec<(Wallet, TrustRecord)> {
+            unimplemented!()
+        }
+
+        fn add_evidence(&self, wallet: &Wallet, evidence: TrustEvidence) {
+            self.add_evidence_parameters
+                .lock()
+                .unwrap()
+                .push((wallet.clone(), evidence));
+        }
+    }
+
+    impl TrustDaoMock {
+        fn add_evidence_parameters(
+            mut self,
+            parameters: &Arc<Mutex<Vec<(Wallet, TrustEvidence)>>>,
+        ) -> Self {
+            self.add_evidence_parameters = parameters.clone();
+            self
+        }
+    }
+
+    #[derive(Default)]
+    pub struct FinancialsDaoMock {
+        snapshot_parameters: Arc<Mutex<Vec<(u64, u64, u64, u64)>>>,
+        snapshot_results: RefCell<Vec<FinancialsSnapshot>>,
+    }
+
+    impl FinancialsDao for FinancialsDaoMock {
+        fn snapshot(
+            &mut self,
+            _now: SystemTime,
+            payable_minimum_amount: u64,
+            payable_maximum_age: u64,
+            receivable_minimum_amount: u64,
+            receivable_maximum_age: u64,
+        ) -> FinancialsSnapshot {
+            self.snapshot_parameters.lock().unwrap().push((
+                payable_minimum_amount,
+                payable_maximum_age,
+                receivable_minimum_amount,
+                receivable_maximum_age,
+            ));
+            self.snapshot_results.borrow_mut().remove(0)
+        }
+    }
+
+    impl FinancialsDaoMock {
+        fn snapshot_parameters(
+            mut self,
+            parameters: &Arc<Mutex<Vec<(u64, u64, u64, u64)>>>,
+        ) -> Self {
+            self.snapshot_parameters = parameters.clone();
+            self
+        }
+
+        fn snapshot_result(self, result: FinancialsSnapshot) -> Self {
+            self.snapshot_results.borrow_mut().push(result);
+            self
+        }
+    }
+
+    pub struct FinancialsDaoFactoryMock {
+        mock: RefCell<Option<FinancialsDaoMock>>,
+    }
+
+    impl FinancialsDaoFactory for FinancialsDaoFactoryMock {
+        fn make(&self) -> Box<dyn FinancialsDao> {
+            Box::new(self.mock.borrow_mut().take().unwrap())
+        }
+    }
+
+    impl FinancialsDaoFactoryMock {
+        fn new(mock: FinancialsDaoMock) -> Self {
+            Self {
+                mock: RefCell::new(Some(mock)),
+            }
+        }
+    }
+
+    pub struct TrustDaoFactoryMock {
+        mock: RefCell<Option<TrustDaoMock>>,
+    }
+
+    impl TrustDaoFactory for TrustDaoFactoryMock {
+        fn make(&self) -> Box<dyn TrustDao> {
+            Box::new(self.mock.borrow_mut().take().unwrap())
+        }
+    }
+
+    impl TrustDaoFactoryMock {
+        fn new(mock: TrustDaoMock) -> Self {
+            Self {
+                mock: RefCell::new(Some(mock)),
+            }
+        }
+    }
+
     pub struct ConfigDaoFactoryMock {
         mock: RefCell<Option<ConfigDaoMock>>,
     }
@@ -1159,43 +1820,258 @@ pub mod tests {
     }
 
     #[test]
-    fn financials_request_produces_financials_response() {
-        let payable_top_records_parameters_arc = Arc::new(Mutex::new(vec![]));
-        let payable_dao = PayableDaoMock::new()
-            .top_records_parameters(&payable_top_records_parameters_arc)
-            .top_records_result(vec![
-                PayableAccount {
-                    wallet: make_wallet("earning 1"),
-                    balance: 12345678,
-                    last_paid_timestamp: SystemTime::now().sub(Duration::from_secs(10000)),
-                    pending_payment_transaction: Some(H256::from_uint(&U256::from(123))),
-                },
-                PayableAccount {
-                    wallet: make_wallet("earning 2"),
-                    balance: 12345679,
-                    last_paid_timestamp: SystemTime::now().sub(Duration::from_secs(10001)),
-                    pending_payment_transaction: None,
+    fn record_usage_charges_what_happened_since_last_time_to_the_day() {
+        let add_usage_parameters_arc = Arc::new(Mutex::new(vec![]));
+        let mut subject = make_subject(None, None, None, None, None);
+        subject.usage_history_dao = Box::new(
+            UsageHistoryDaoMock::default().add_usage_parameters(&add_usage_parameters_arc),
+        );
+        let then = from_time_t(1_604_275_000); // 2020-11-01T23:56:40Z
+        subject.usage_recorded = (
+            then,
+            UsageCounters {
+                routed_bytes: 1000,
+                earned_gwei: 2000,
+                neighbors_gained: 3,
+                neighbors_lost: 1,
+            },
+        );
+        let now = then + Duration::from_secs(600);
+        let counters = UsageCounters {
+            routed_bytes: 1500,
+            earned_gwei: 2700,
+            neighbors_gained: 5,
+            neighbors_lost: 1,
+        };
+
+        subject.record_usage(now, counters);
+
+        assert_eq!(
+            *add_usage_parameters_arc.lock().unwrap(),
+            vec![DailyUsage {
+                day: "2020-11-02".to_string(),
+                uptime_seconds: 600,
+                routed_bytes: 500,
+                earned_gwei: 700,
+                neighbors_gained: 2,
+                neighbors_lost: 0,
+            }]
+        );
+        assert_eq!(subject.usage_recorded, (now, counters));
+    }
+
+    #[test]
+    fn usage_history_request_produces_usage_history_response() {
+        let add_usage_parameters_arc = Arc::new(Mutex::new(vec![]));
+        let history_parameters_arc = Arc::new(Mutex::new(vec![]));
+        let usage_history_dao = UsageHistoryDaoMock::default()
+            .add_usage_parameters(&add_usage_parameters_arc)
+            .history_parameters(&history_parameters_arc)
+            .history_result(vec![DailyUsage {
+                day: "2020-11-02".to_string(),
+                uptime_seconds: 86400,
+                routed_bytes: 1234567,
+                earned_gwei: 2345678,
+                neighbors_gained: 3,
+                neighbors_lost: 2,
+            }]);
+        let system = System::new("test");
+        let mut subject = make_subject(None, None, None, None, None);
+        subject.usage_history_dao = Box::new(usage_history_dao);
+        let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
+        let subject_addr = subject.start();
+        let peer_actors = peer_actors_builder().ui_gateway(ui_gateway).build();
+        subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+        let ui_message = NodeFromUiMessage {
+            client_id: 1234,
+            body: UiUsageHistoryRequest { days: 7 }.tmb(2222),
+        };
+
+        subject_addr.try_send(ui_message).unwrap();
+
+        System::current().stop();
+        system.run();
+        assert_eq!(add_usage_parameters_arc.lock().unwrap().len(), 1);
+        assert_eq!(*history_parameters_arc.lock().unwrap(), vec![7]);
+        let ui_gateway_recording = ui_gateway_recording_arc.lock().unwrap();
+        let response = ui_gateway_recording.get_record::<NodeToUiMessage>(0);
+        assert_eq!(response.target, MessageTarget::ClientId(1234));
+        let (payload, context_id) = UiUsageHistoryResponse::fmb(response.body.clone()).unwrap();
+        assert_eq!(context_id, 2222);
+        assert_eq!(
+            payload,
+            UiUsageHistoryResponse {
+                days: vec![UiUsageDay {
+                    date: "2020-11-02".to_string(),
+                    uptime_seconds: 86400,
+                    routed_bytes: 1234567,
+                    earned_gwei: 2345678,
+                    neighbors_gained: 3,
+                    neighbors_lost: 2,
+                }]
+            }
+        );
+    }
+
+    fn make_data_cap_config(monthly_bytes: u64) -> DataCapConfig {
+        DataCapConfig {
+            monthly_bytes,
+            rates: vec![],
+        }
+    }
+
+    #[test]
+    fn data_cap_starts_with_what_was_routed_earlier_this_month() {
+        let history_parameters_arc = Arc::new(Mutex::new(vec![]));
+        let usage_history_dao = UsageHistoryDaoMock::default()
+            .history_parameters(&history_parameters_arc)
+            .history_result(vec![
+                DailyUsage {
+                    day: "2020-11-02".to_string(),
+                    routed_bytes: 800,
+                    ..DailyUsage::default()
                 },
-            ])
-            .total_result(23456789);
-        let receivable_top_records_parameters_arc = Arc::new(Mutex::new(vec![]));
-        let receivable_dao = ReceivableDaoMock::new()
-            .top_records_parameters(&receivable_top_records_parameters_arc)
-            .top_records_result(vec![
-                ReceivableAccount {
-                    wallet: make_wallet("consuming 1"),
-                    balance: 87654321,
-                    last_received_timestamp: SystemTime::now().sub(Duration::from_secs(20000)),
+                DailyUsage {
+                    day: "2020-11-01".to_string(),
+                    routed_bytes: 150,
+                    ..DailyUsage::default()
                 },
-                ReceivableAccount {
-                    wallet: make_wallet("consuming 2"),
-                    balance: 87654322,
-                    last_received_timestamp: SystemTime::now().sub(Duration::from_secs(20001)),
+                DailyUsage {
+                    day: "2020-10-31".to_string(),
+                    routed_bytes: 5000,
+                    ..DailyUsage::default()
                 },
-            ])
-            .total_result(98765432);
+            ]);
+        let mut config = BootstrapperConfig::new();
+        config.data_cap_opt = Some(make_data_cap_config(1000));
+        let mut subject = make_subject(Some(config), None, None, None, None);
+        subject.usage_history_dao = Box::new(usage_history_dao);
+
+        subject.start_data_cap(Local.ymd(2020, 11, 15).and_hms(12, 0, 0));
+
+        let tracker = subject.data_cap_opt.as_ref().unwrap();
+        assert_eq!(tracker.used_bytes(), 950);
+        assert_eq!(tracker.month(), "2020-11");
+        assert_eq!(data_cap::status(), DataCapStatus::Throttled);
+        assert_eq!(*history_parameters_arc.lock().unwrap(), vec![31]);
+        data_cap::set_status(DataCapStatus::Open);
+    }
+
+    #[test]
+    fn data_cap_is_left_alone_without_a_cap() {
+        let history_parameters_arc = Arc::new(Mutex::new(vec![]));
+        let mut subject = make_subject(None, None, None, None, None);
+        subject.usage_history_dao =
+            Box::new(UsageHistoryDaoMock::default().history_parameters(&history_parameters_arc));
+
+        subject.start_data_cap(Local::now());
+        subject.charge_data_cap(1_000_000, Local::now());
+
+        assert_eq!(subject.data_cap_opt.is_none(), true);
+        assert_eq!(history_parameters_arc.lock().unwrap().is_empty(), true);
+        assert_eq!(data_cap::status(), DataCapStatus::Open);
+    }
+
+    #[test]
+    fn service_provided_is_charged_against_the_data_cap() {
+        init_test_logging();
+        let now = Local.ymd(2020, 11, 15).and_hms(12, 0, 0);
+        let mut subject = make_subject(None, None, None, None, None);
+        subject.data_cap_opt = Some(DataCapTracker::new(make_data_cap_config(1000), now, 0));
+
+        subject.charge_data_cap(900, now);
+        let throttled = data_cap::status();
+        subject.charge_data_cap(100, now);
+        let exhausted = data_cap::status();
+        subject.roll_over_data_cap(Local.ymd(2020, 12, 1).and_hms(0, 0, 1));
+        let reopened = data_cap::status();
+
+        assert_eq!(throttled, DataCapStatus::Throttled);
+        assert_eq!(exhausted, DataCapStatus::Exhausted);
+        assert_eq!(reopened, DataCapStatus::Open);
+        let tlh = TestLogHandler::new();
+        tlh.exists_log_containing(
+            "WARN: Accountant: 90% of this month's data cap is used; taking on no new exit streams",
+        );
+        tlh.exists_log_containing("WARN: Accountant: This month's data cap is used up; neither routing nor exiting for other Nodes until next month");
+        tlh.exists_log_containing(
+            "INFO: Accountant: Data cap has room again; serving other Nodes as usual",
+        );
+    }
+
+    #[test]
+    fn data_cap_request_produces_data_cap_response() {
+        let system = System::new("data_cap_request_produces_data_cap_response");
+        let mut subject = make_subject(None, None, None, None, None);
+        let now = Local::now();
+        subject.data_cap_opt = Some(DataCapTracker::new(make_data_cap_config(1000), now, 250));
+        let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
+        let subject_addr = subject.start();
+        let peer_actors = peer_actors_builder().ui_gateway(ui_gateway).build();
+        subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+        let ui_message = NodeFromUiMessage {
+            client_id: 1234,
+            body: UiDataCapRequest {}.tmb(2222),
+        };
+
+        subject_addr.try_send(ui_message).unwrap();
+
+        System::current().stop();
+        system.run();
+        let ui_gateway_recording = ui_gateway_recording_arc.lock().unwrap();
+        let response = ui_gateway_recording.get_record::<NodeToUiMessage>(0);
+        assert_eq!(response.target, MessageTarget::ClientId(1234));
+        let (payload, context_id) = UiDataCapResponse::fmb(response.body.clone()).unwrap();
+        assert_eq!(context_id, 2222);
+        assert_eq!(
+            payload,
+            UiDataCapResponse {
+                month: DataCapTracker::month_of(now),
+                monthly_bytes_opt: Some(1000),
+                used_bytes_opt: Some(250),
+                remaining_bytes_opt: Some(750),
+                status: "open".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn financials_request_produces_financials_response() {
+        let snapshot_parameters_arc = Arc::new(Mutex::new(vec![]));
+        let financials_dao = FinancialsDaoMock::default()
+            .snapshot_parameters(&snapshot_parameters_arc)
+            .snapshot_result(FinancialsSnapshot {
+                payables: vec![
+                    PayableAccount {
+                        wallet: make_wallet("earning 1"),
+                        balance: 12345678,
+                        last_paid_timestamp: SystemTime::now().sub(Duration::from_secs(10000)),
+                        pending_payment_transaction: Some(H256::from_uint(&U256::from(123))),
+                    },
+                    PayableAccount {
+                        wallet: make_wallet("earning 2"),
+                        balance: 12345679,
+                        last_paid_timestamp: SystemTime::now().sub(Duration::from_secs(10001)),
+                        pending_payment_transaction: None,
+                    },
+                ],
+                total_payable: 23456789,
+                receivables: vec![
+                    ReceivableAccount {
+                        wallet: make_wallet("consuming 1"),
+                        balance: 87654321,
+                        last_received_timestamp: SystemTime::now().sub(Duration::from_secs(20000)),
+                    },
+                    ReceivableAccount {
+                        wallet: make_wallet("consuming 2"),
+                        balance: 87654322,
+                        last_received_timestamp: SystemTime::now().sub(Duration::from_secs(20001)),
+                    },
+                ],
+                total_receivable: 98765432,
+            });
         let system = System::new("test");
-        let subject = make_subject(
+        let mut subject = make_subject(
             Some(bc_from_ac_plus_earning_wallet(
                 AccountantConfig {
                     payable_scan_interval: Duration::from_millis(10_000),
@@ -1203,11 +2079,12 @@ pub mod tests {
                 },
                 make_wallet("some_wallet_address"),
             )),
-            Some(payable_dao),
-            Some(receivable_dao),
+            None,
+            None,
             None,
             None,
         );
+        subject.financials_dao = Box::new(financials_dao);
         let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
         let subject_addr = subject.start();
         let peer_actors = peer_actors_builder().ui_gateway(ui_gateway).build();
@@ -1225,11 +2102,8 @@ pub mod tests {
 
         System::current().stop();
         system.run();
-        let payable_top_records_parameters = payable_top_records_parameters_arc.lock().unwrap();
-        assert_eq!(*payable_top_records_parameters, vec![(50001, 50002)]);
-        let receivable_top_records_parameters =
-            receivable_top_records_parameters_arc.lock().unwrap();
-        assert_eq!(*receivable_top_records_parameters, vec![(50003, 50004)]);
+        let snapshot_parameters = snapshot_parameters_arc.lock().unwrap();
+        assert_eq!(*snapshot_parameters, vec![(50001, 50002, 50003, 50004)]);
         let ui_gateway_recording = ui_gateway_recording_arc.lock().unwrap();
         let response = ui_gateway_recording.get_record::<NodeToUiMessage>(0);
         assert_eq!(response.target, MessageTarget::ClientId(1234));
@@ -1371,6 +2245,59 @@ pub mod tests {
         assert_eq!(actual, &expected_payment);
     }
 
+    #[test]
+    fn accountant_broadcasts_sent_payments_once_they_are_recorded() {
+        let payable_dao = PayableDaoMock::new()
+            .non_pending_payables_result(vec![])
+            .payment_sent_result(Ok(()));
+        let system = System::new("accountant_broadcasts_sent_payments_once_they_are_recorded");
+        let accountant = make_subject(
+            Some(bc_from_ac_plus_earning_wallet(
+                AccountantConfig {
+                    payable_scan_interval: Duration::from_millis(10_000),
+                    payment_received_scan_interval: Duration::from_millis(10_000),
+                },
+                make_wallet("some_wallet_address"),
+            )),
+            Some(payable_dao),
+            None,
+            None,
+            None,
+        );
+        let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
+        let subject_addr = accountant.start();
+        let peer_actors = peer_actors_builder().ui_gateway(ui_gateway).build();
+        subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+        let payment = Payment::new(
+            make_wallet("paying_you"),
+            1234,
+            H256::from("transaction_hash".keccak256()),
+        );
+
+        subject_addr
+            .try_send(SentPayments {
+                payments: vec![Ok(payment.clone())],
+            })
+            .unwrap();
+
+        System::current().stop();
+        system.run();
+        let ui_gateway_recording = ui_gateway_recording_arc.lock().unwrap();
+        assert_eq!(
+            ui_gateway_recording.get_record::<NodeToUiMessage>(0),
+            &NodeToUiMessage {
+                target: MessageTarget::AllClients,
+                body: UiPaymentBroadcast {
+                    direction: "sent".to_string(),
+                    wallet: payment.to.to_string(),
+                    amount: 1234,
+                    transaction_opt: Some(format!("0x{:0X}", payment.transaction)),
+                }
+                .tmb(0),
+            }
+        );
+    }
+
     #[test]
     fn accountant_logs_warning_when_handle_sent_payments_encounters_a_blockchain_error() {
         init_test_logging();
@@ -1456,11 +2383,12 @@ pub mod tests {
                 .accountant(accountant_mock)
                 .build();
             let subject = make_subject(
-                Some(bc_from_ac_plus_earning_wallet(
+                Some(bc_from_ac_plus_wallets(
                     AccountantConfig {
                         payable_scan_interval: Duration::from_millis(100),
                         payment_received_scan_interval: Duration::from_secs(10_000),
                     },
+                    make_wallet("consumer"),
                     earning_wallet.clone(),
                 )),
                 Some(payable_dao),
@@ -1532,11 +2460,12 @@ pub mod tests {
                 .accountant(accountant_mock)
                 .build();
             let subject = make_subject(
-                Some(bc_from_ac_plus_earning_wallet(
+                Some(bc_from_ac_plus_wallets(
                     AccountantConfig {
                         payable_scan_interval: Duration::from_millis(100),
                         payment_received_scan_interval: Duration::from_secs(10_000),
                     },
+                    make_wallet("consumer"),
                     earning_wallet.clone(),
                 )),
                 Some(payable_dao),
@@ -1569,6 +2498,8 @@ pub mod tests {
             block_number: 7u64,
             from: paying_wallet.clone(),
             gwei_amount: amount,
+            transaction_hash: H256::from([10; 32]),
+            confirmations: 1,
         }];
         let blockchain_bridge =
             Recorder::new().retrieve_transactions_response(Ok(expected_transactions.clone()));
@@ -1760,6 +2691,8 @@ pub mod tests {
             block_number: 7u64,
             from: wallet.clone(),
             gwei_amount,
+            transaction_hash: H256::from([11; 32]),
+            confirmations: 1,
         };
         let more_money_received_params_arc = Arc::new(Mutex::new(vec![]));
         let receivable_dao = ReceivableDaoMock::new()
@@ -1783,25 +2716,127 @@ pub mod tests {
         let system = System::new("accountant_receives_new_payments_to_the_receivables_dao");
         let subject = accountant.start();
 
-        subject
+        subject
+            .try_send(ReceivedPayments {
+                payments: vec![expected_payment.clone(), expected_payment.clone()],
+            })
+            .expect("unexpected actix error");
+        System::current().stop();
+        system.run();
+        let more_money_received_params = more_money_received_params_arc.lock().unwrap();
+        assert_eq!(1, more_money_received_params.len());
+
+        let more_money_received_params = more_money_received_params.get(0).unwrap();
+        assert_eq!(2, more_money_received_params.len());
+
+        let first_payment = more_money_received_params.get(0).unwrap();
+        assert_eq!(expected_payment.from, first_payment.from);
+        assert_eq!(gwei_amount, first_payment.gwei_amount);
+        let second_payment = more_money_received_params.get(1).unwrap();
+        assert_eq!(expected_payment.from, second_payment.from);
+        assert_eq!(gwei_amount, second_payment.gwei_amount);
+    }
+
+    #[test]
+    fn charges_and_payments_are_credited_to_the_earnings_cache() {
+        let wallet = make_wallet("charges_and_payments_are_credited");
+        let mut subject = make_subject(
+            None,
+            Some(PayableDaoMock::new()),
+            Some(
+                ReceivableDaoMock::new()
+                    .more_money_receivable_result(Ok(()))
+                    .more_money_received_result(Ok(())),
+            ),
+            None,
+            None,
+        );
+
+        subject.record_service_provided(10, 2, 100, &wallet);
+        subject.handle_received_payments(ReceivedPayments {
+            payments: vec![Transaction {
+                block_number: 7,
+                from: wallet.clone(),
+                gwei_amount: 150,
+                transaction_hash: H256::from([11; 32]),
+                confirmations: 1,
+            }],
+        });
+
+        assert_eq!(
+            EARNINGS_CACHE.earnings(&wallet),
+            Earnings {
+                paid_gwei: 150,
+                charged_gwei: 210
+            }
+        );
+    }
+
+    #[test]
+    fn accountant_broadcasts_received_payments_as_soon_as_it_sees_them() {
+        let receivable_dao = ReceivableDaoMock::new().more_money_received_result(Ok(()));
+        let system = System::new("accountant_broadcasts_received_payments_as_soon_as_it_sees_them");
+        let accountant = make_subject(
+            Some(bc_from_ac_plus_earning_wallet(
+                AccountantConfig {
+                    payable_scan_interval: Duration::from_secs(10_000),
+                    payment_received_scan_interval: Duration::from_secs(10_000),
+                },
+                make_wallet("earner3000"),
+            )),
+            Some(PayableDaoMock::new().non_pending_payables_result(vec![])),
+            Some(receivable_dao),
+            None,
+            None,
+        );
+        let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
+        let subject_addr = accountant.start();
+        let peer_actors = peer_actors_builder().ui_gateway(ui_gateway).build();
+        subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+        let payment = Transaction {
+            block_number: 7u64,
+            from: make_wallet("paying_you"),
+            gwei_amount: 4321,
+            transaction_hash: H256::from("transaction_hash".keccak256()),
+            confirmations: 3,
+        };
+
+        subject_addr
             .try_send(ReceivedPayments {
-                payments: vec![expected_payment.clone(), expected_payment.clone()],
+                payments: vec![payment.clone()],
             })
-            .expect("unexpected actix error");
+            .unwrap();
+
         System::current().stop();
         system.run();
-        let more_money_received_params = more_money_received_params_arc.lock().unwrap();
-        assert_eq!(1, more_money_received_params.len());
-
-        let more_money_received_params = more_money_received_params.get(0).unwrap();
-        assert_eq!(2, more_money_received_params.len());
-
-        let first_payment = more_money_received_params.get(0).unwrap();
-        assert_eq!(expected_payment.from, first_payment.from);
-        assert_eq!(gwei_amount, first_payment.gwei_amount);
-        let second_payment = more_money_received_params.get(1).unwrap();
-        assert_eq!(expected_payment.from, second_payment.from);
-        assert_eq!(gwei_amount, second_payment.gwei_amount);
+        let ui_gateway_recording = ui_gateway_recording_arc.lock().unwrap();
+        assert_eq!(
+            ui_gateway_recording.get_record::<NodeToUiMessage>(0),
+            &NodeToUiMessage {
+                target: MessageTarget::AllClients,
+                body: UiPaymentBroadcast {
+                    direction: "received".to_string(),
+                    wallet: payment.from.to_string(),
+                    amount: 4321,
+                    transaction_opt: None,
+                }
+                .tmb(0),
+            }
+        );
+        assert_eq!(
+            ui_gateway_recording.get_record::<NodeToUiMessage>(1),
+            &NodeToUiMessage {
+                target: MessageTarget::AllClients,
+                body: UiPaymentReceivedBroadcast {
+                    payer: payment.from.to_string(),
+                    amount: 4321,
+                    transaction: format!("0x{:0X}", payment.transaction_hash),
+                    confirmations: 3,
+                }
+                .tmb(0),
+            }
+        );
+        assert_eq!(ui_gateway_recording.len(), 2);
     }
 
     #[test]
@@ -1958,14 +2993,30 @@ pub mod tests {
         assert_eq!(blockchain_bridge_recordings.len(), 0);
     }
 
+    #[test]
+    fn a_payable_paid_in_the_clocks_future_is_not_due_until_the_clock_catches_up() {
+        let now = SystemTime::now();
+        let payable = PayableAccount {
+            wallet: make_wallet("wallet"),
+            balance: PAYMENT_CURVES.balance_to_decrease_from_gwub + 1,
+            last_paid_timestamp: now + Duration::from_secs(3600),
+            pending_payment_transaction: None,
+        };
+
+        let result = Accountant::should_pay(&payable, now);
+
+        assert_eq!(result, false);
+    }
+
     #[test]
     fn scan_for_payables_message_triggers_payment_for_balances_over_the_curve() {
         init_test_logging();
-        let config = bc_from_ac_plus_earning_wallet(
+        let config = bc_from_ac_plus_wallets(
             AccountantConfig {
                 payable_scan_interval: Duration::from_millis(100),
                 payment_received_scan_interval: Duration::from_millis(1_000),
             },
+            make_wallet("consuming"),
             make_wallet("mine"),
         );
         let now = to_time_t(SystemTime::now());
@@ -2024,10 +3075,45 @@ pub mod tests {
         );
     }
 
+    #[test]
+    fn earning_only_node_does_not_try_to_pay_what_it_owes() {
+        init_test_logging();
+        let config = bc_from_ac_plus_earning_wallet(
+            AccountantConfig {
+                payable_scan_interval: Duration::from_secs(100),
+                payment_received_scan_interval: Duration::from_secs(1000),
+            },
+            make_wallet("mine"),
+        );
+        let now = to_time_t(SystemTime::now());
+        let payable_dao = PayableDaoMock::new().non_pending_payables_result(vec![PayableAccount {
+            wallet: make_wallet("wallet0"),
+            balance: PAYMENT_CURVES.balance_to_decrease_from_gwub + 1,
+            last_paid_timestamp: from_time_t(now - PAYMENT_CURVES.payment_suggested_after_sec - 10),
+            pending_payment_transaction: None,
+        }]);
+        let (blockchain_bridge, _, blockchain_bridge_recordings_arc) = make_recorder();
+        let system = System::new("earning_only_node_does_not_try_to_pay_what_it_owes");
+        let mut subject = make_subject(Some(config), Some(payable_dao), None, None, None);
+        subject.report_accounts_payable_sub = Some(
+            blockchain_bridge
+                .start()
+                .recipient::<ReportAccountsPayable>(),
+        );
+
+        subject.scan_for_payables();
+
+        System::current().stop();
+        system.run();
+        assert_eq!(blockchain_bridge_recordings_arc.lock().unwrap().len(), 0);
+        TestLogHandler::new().exists_log_containing(
+            "DEBUG: Accountant: 1 payable(s) due, but this Node is earning-only and has no consuming wallet to pay from",
+        );
+    }
+
     #[test]
     fn payment_received_scan_triggers_scan_for_delinquencies() {
-        let ban_parameters_arc = Arc::new(Mutex::new(vec![]));
-        let ban_parameters_arc_inner = ban_parameters_arc.clone();
+        let (proxy_client, proxy_client_awaiter, proxy_client_recording_arc) = make_recorder();
         let blockchain_bridge = Recorder::new().retrieve_transactions_response(Ok(vec![]));
         thread::spawn(move || {
             let system = System::new("payment_received_scan_triggers_scan_for_delinquencies");
@@ -2043,9 +3129,7 @@ pub mod tests {
             let receivable_dao = ReceivableDaoMock::new()
                 .new_delinquencies_result(vec![make_receivable_account(1234, true)])
                 .paid_delinquencies_result(vec![]);
-            let banned_dao = BannedDaoMock::new()
-                .ban_list_result(vec![])
-                .ban_parameters(&ban_parameters_arc_inner);
+            let banned_dao = BannedDaoMock::new().ban_list_result(vec![]);
             let subject = make_subject(
                 Some(config),
                 Some(payable_dao),
@@ -2055,6 +3139,7 @@ pub mod tests {
             );
             let peer_actors = peer_actors_builder()
                 .blockchain_bridge(blockchain_bridge)
+                .proxy_client(proxy_client)
                 .build();
             let subject_addr: Addr<Accountant> = subject.start();
             let subject_subs = Accountant::make_subs_from(&subject_addr);
@@ -2065,12 +3150,30 @@ pub mod tests {
             system.run();
         });
 
-        thread::sleep(Duration::from_millis(200));
-
-        let ban_parameters = ban_parameters_arc.lock().unwrap();
+        proxy_client_awaiter.await_message_count(1);
+        let proxy_client_recording = proxy_client_recording_arc.lock().unwrap();
+        let notice = proxy_client_recording.get_record::<PaymentOverdue_0v1>(0);
         assert_eq!(
             "0x00000000000000000077616c6c65743132333464",
-            &format!("{:#x}", &ban_parameters[0].address())
+            &format!("{:#x}", &notice.debtor.address())
+        );
+    }
+
+    #[test]
+    fn a_local_proxy_doesnt_scan_for_payments() {
+        init_test_logging();
+        let new_delinquencies_parameters_arc = Arc::new(Mutex::new(vec![]));
+        let receivable_dao = ReceivableDaoMock::new()
+            .new_delinquencies_parameters(&new_delinquencies_parameters_arc);
+        let mut config = BootstrapperConfig::new();
+        config.local_proxy = true;
+        let mut subject = make_subject(Some(config), None, Some(receivable_dao), None, None);
+
+        subject.handle_start_message();
+
+        assert!(new_delinquencies_parameters_arc.lock().unwrap().is_empty());
+        TestLogHandler::new().exists_log_containing(
+            "INFO: Accountant: Running as a local proxy: not scanning for payments",
         );
     }
 
@@ -2136,9 +3239,17 @@ pub mod tests {
             Some(banned_dao),
             None,
         );
+        let dunned_long_ago = SystemTime::now() - DUNNING_PERIOD - Duration::from_secs(1);
+        subject
+            .dunned
+            .insert(newly_banned_1.wallet.clone(), dunned_long_ago);
+        subject
+            .dunned
+            .insert(newly_banned_2.wallet.clone(), dunned_long_ago);
 
         subject.scan_for_delinquencies();
 
+        assert!(subject.dunned.is_empty());
         let new_delinquencies_parameters: MutexGuard<Vec<(SystemTime, PaymentCurves)>> =
             new_delinquencies_parameters_arc.lock().unwrap();
         assert_eq!(PAYMENT_CURVES.clone(), new_delinquencies_parameters[0].1);
@@ -2160,6 +3271,325 @@ pub mod tests {
         tlh.exists_log_matching("INFO: Accountant: Wallet 0x00000000000000000077616c6c6574343536376e \\(balance: 4567 MASQ, age: \\d+ sec\\) is no longer delinquent: unbanned");
     }
 
+    #[test]
+    fn scan_for_delinquencies_sends_a_payment_overdue_notice_before_banning() {
+        init_test_logging();
+        let then = SystemTime::UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+        let clock = VirtualClock::new(then);
+        let delinquent = ReceivableAccount {
+            wallet: make_wallet("delinquent"),
+            balance: 1_234_567_890,
+            last_received_timestamp: then - Duration::from_secs(200_000),
+        };
+        let paid_up = ReceivableAccount {
+            wallet: make_wallet("paid up"),
+            balance: 2_345_678_901,
+            last_received_timestamp: then - Duration::from_secs(200_000),
+        };
+        let receivable_dao = ReceivableDaoMock::new()
+            .new_delinquencies_result(vec![delinquent.clone(), paid_up.clone()])
+            .new_delinquencies_result(vec![delinquent.clone()])
+            .new_delinquencies_result(vec![delinquent.clone()]);
+        let ban_parameters_arc = Arc::new(Mutex::new(vec![]));
+        let banned_dao = BannedDaoMock::new().ban_parameters(&ban_parameters_arc);
+        let mut subject = make_subject(
+            Some(bc_from_ac_plus_earning_wallet(
+                AccountantConfig {
+                    payable_scan_interval: Duration::from_secs(100),
+                    payment_received_scan_interval: Duration::from_secs(1000),
+                },
+                make_wallet("mine"),
+            )),
+            None,
+            Some(receivable_dao),
+            Some(banned_dao),
+            None,
+        );
+        subject.set_clock(Arc::new(clock.clone()));
+        let system =
+            System::new("scan_for_delinquencies_sends_a_payment_overdue_notice_before_banning");
+        let (proxy_client, _, proxy_client_recording_arc) = make_recorder();
+        subject.payment_overdue_sub = Some(proxy_client.start().recipient());
+
+        subject.scan_for_delinquencies();
+        let bans_after_notice = ban_parameters_arc.lock().unwrap().len();
+        clock.advance(DUNNING_PERIOD - Duration::from_secs(1));
+        subject.scan_for_delinquencies();
+        let bans_before_period_is_up = ban_parameters_arc.lock().unwrap().len();
+        clock.advance(Duration::from_secs(1));
+        subject.scan_for_delinquencies();
+
+        System::current().stop();
+        system.run();
+        assert_eq!(bans_after_notice, 0);
+        assert_eq!(bans_before_period_is_up, 0);
+        assert_eq!(
+            *ban_parameters_arc.lock().unwrap(),
+            vec![delinquent.wallet.clone()]
+        );
+        assert!(subject.dunned.is_empty());
+        let proxy_client_recording = proxy_client_recording_arc.lock().unwrap();
+        assert_eq!(proxy_client_recording.len(), 2);
+        assert_eq!(
+            proxy_client_recording.get_record::<PaymentOverdue_0v1>(0),
+            &PaymentOverdue_0v1 {
+                creditor: make_wallet("mine"),
+                debtor: delinquent.wallet.clone(),
+                balance: 1_234_567_890,
+                age_sec: 200_000,
+                ban_in_sec: DUNNING_PERIOD.as_secs(),
+            }
+        );
+        assert_eq!(
+            proxy_client_recording
+                .get_record::<PaymentOverdue_0v1>(1)
+                .debtor,
+            paid_up.wallet
+        );
+        TestLogHandler::new().exists_log_containing(&format!(
+            "INFO: Accountant: Wallet {} (balance: 1.23456789 MASQ, age: 200000 sec) is delinquent: sent a payment-overdue notice; banning it in {} sec unless it pays",
+            delinquent.wallet,
+            DUNNING_PERIOD.as_secs()
+        ));
+    }
+
+    #[test]
+    fn delinquent_wallets_are_dunned_for_longer_the_more_they_are_trusted() {
+        let trusted = make_wallet("trusted debtor");
+        let distrusted = make_wallet("distrusted debtor");
+        TRUST_CACHE.insert(
+            &trusted,
+            TrustRecord {
+                payments_on_time: 20,
+                payments_overdue: 0,
+                accurate_gossip: 100,
+                inaccurate_gossip: 0,
+                uptime_seconds: 30 * 24 * 3600,
+            },
+        );
+        TRUST_CACHE.insert(
+            &distrusted,
+            TrustRecord {
+                payments_overdue: 1000,
+                inaccurate_gossip: 1000,
+                ..TrustRecord::default()
+            },
+        );
+
+        assert_eq!(
+            Accountant::dunning_period(&make_wallet("unknown debtor"), false),
+            DUNNING_PERIOD
+        );
+        assert_eq!(
+            Accountant::dunning_period(&trusted, false),
+            Duration::from_secs(36828)
+        );
+        assert_eq!(
+            Accountant::dunning_period(&distrusted, false),
+            Duration::from_secs(8640)
+        );
+    }
+
+    #[test]
+    fn new_debtors_get_the_credit_attestations_earn_them_only_when_heeded() {
+        let newcomer = make_wallet("attested new debtor");
+        let attestor = make_wallet("debtor attestor");
+        ATTESTATION_CACHE.accept(
+            &attestor,
+            vec![Attestation::new(
+                newcomer.clone(),
+                10,
+                0,
+                &attestor,
+                main_cryptde(),
+            )],
+            Instant::now(),
+        );
+
+        assert_eq!(Accountant::dunning_period(&newcomer, false), DUNNING_PERIOD);
+        assert_eq!(
+            Accountant::dunning_period(&newcomer, true),
+            Duration::from_secs(28404)
+        );
+    }
+
+    #[test]
+    fn a_ban_counts_against_trust_and_a_payment_without_a_notice_counts_for_it() {
+        let then = SystemTime::UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+        let clock = VirtualClock::new(then);
+        let delinquent = ReceivableAccount {
+            wallet: make_wallet("banned for a ban"),
+            balance: 1_234_567_890,
+            last_received_timestamp: then - Duration::from_secs(200_000),
+        };
+        let dunned_payer = ReceivableAccount {
+            wallet: make_wallet("dunned payer"),
+            ..delinquent.clone()
+        };
+        let receivable_dao = ReceivableDaoMock::new()
+            .new_delinquencies_result(vec![delinquent.clone(), dunned_payer.clone()]);
+        let add_evidence_parameters_arc = Arc::new(Mutex::new(vec![]));
+        let mut subject = make_subject(
+            None,
+            None,
+            Some(receivable_dao),
+            Some(BannedDaoMock::new()),
+            None,
+        );
+        subject.set_clock(Arc::new(clock.clone()));
+        subject.trust_dao =
+            Box::new(TrustDaoMock::default().add_evidence_parameters(&add_evidence_parameters_arc));
+        subject.dunned.insert(
+            delinquent.wallet.clone(),
+            then - DUNNING_PERIOD - Duration::from_secs(1),
+        );
+        subject.dunned.insert(dunned_payer.wallet.clone(), then);
+        let payment = |from: &str| Transaction {
+            block_number: 7,
+            from: make_wallet(from),
+            gwei_amount: 42,
+            transaction_hash: H256::from([11; 32]),
+            confirmations: 1,
+        };
+
+        subject.scan_for_delinquencies();
+        subject.handle_received_payments(ReceivedPayments {
+            payments: vec![payment("prompt payer"), payment("dunned payer")],
+        });
+
+        assert_eq!(
+            *add_evidence_parameters_arc.lock().unwrap(),
+            vec![
+                (delinquent.wallet.clone(), TrustEvidence::PaymentOverdue),
+                (make_wallet("prompt payer"), TrustEvidence::PaidOnTime),
+            ]
+        );
+    }
+
+    #[test]
+    fn payment_overdue_notices_about_the_consuming_wallet_are_broadcast() {
+        init_test_logging();
+        let consuming_wallet = make_paying_wallet(b"consuming");
+        let mut config = bc_from_ac_plus_earning_wallet(
+            AccountantConfig {
+                payable_scan_interval: Duration::from_secs(100),
+                payment_received_scan_interval: Duration::from_secs(1000),
+            },
+            make_wallet("earning"),
+        );
+        config.consuming_wallet = Some(consuming_wallet.clone());
+        let notice = PaymentOverdue_0v1 {
+            creditor: make_wallet("creditor"),
+            debtor: consuming_wallet.as_address_wallet(),
+            balance: 1_234_567_890,
+            age_sec: 200_000,
+            ban_in_sec: 21_600,
+        };
+        let stranger_notice = PaymentOverdue_0v1 {
+            debtor: make_wallet("stranger"),
+            ..notice.clone()
+        };
+        let system =
+            System::new("payment_overdue_notices_about_the_consuming_wallet_are_broadcast");
+        let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
+        let subject = make_subject(Some(config), None, None, None, None);
+        let subject_addr = subject.start();
+        let subject_subs = Accountant::make_subs_from(&subject_addr);
+        let peer_actors = peer_actors_builder().ui_gateway(ui_gateway).build();
+        subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+        let package = |notice: PaymentOverdue_0v1| {
+            ExpiredCoresPackage::new(
+                SocketAddr::from_str("1.2.3.4:1234").unwrap(),
+                None,
+                Route { hops: vec![] },
+                notice,
+                0,
+            )
+        };
+
+        subject_subs
+            .payment_overdue_from_hopper
+            .try_send(package(stranger_notice.clone()))
+            .unwrap();
+        subject_subs
+            .payment_overdue_from_hopper
+            .try_send(package(notice.clone()))
+            .unwrap();
+
+        System::current().stop();
+        system.run();
+        let ui_gateway_recording = ui_gateway_recording_arc.lock().unwrap();
+        assert_eq!(ui_gateway_recording.len(), 1);
+        assert_eq!(
+            ui_gateway_recording.get_record::<NodeToUiMessage>(0),
+            &NodeToUiMessage {
+                target: AllClients,
+                body: UiPaymentOverdueBroadcast {
+                    creditor: notice.creditor.to_string(),
+                    debtor: notice.debtor.to_string(),
+                    balance: 1_234_567_890,
+                    age_seconds: 200_000,
+                    ban_in_seconds: 21_600,
+                }
+                .tmb(0),
+            }
+        );
+        let tlh = TestLogHandler::new();
+        tlh.exists_log_containing(&format!(
+            "WARN: Accountant: Ignoring notice from wallet {} that wallet {} owes it 1.23456789 MASQ: that isn't this Node's consuming wallet",
+            notice.creditor, stranger_notice.debtor
+        ));
+        tlh.exists_log_containing(&format!(
+            "WARN: Accountant: Wallet {} says this Node's consuming wallet {} has owed it 1.23456789 MASQ for 200000 sec, and will stop serving this Node in 21600 sec unless it's paid",
+            notice.creditor, notice.debtor
+        ));
+    }
+
+    #[test]
+    fn scan_for_delinquencies_judges_debts_by_the_accountants_clock() {
+        let then = SystemTime::UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+        let clock = VirtualClock::new(then);
+        let new_delinquencies_parameters_arc = Arc::new(Mutex::new(vec![]));
+        let receivable_dao = ReceivableDaoMock::new()
+            .new_delinquencies_parameters(&new_delinquencies_parameters_arc)
+            .new_delinquencies_result(vec![])
+            .paid_delinquencies_result(vec![]);
+        let mut subject = make_subject(None, None, Some(receivable_dao), None, None);
+        subject.set_clock(Arc::new(clock.clone()));
+        clock.advance(Duration::from_secs(30 * 24 * 3600));
+
+        subject.scan_for_delinquencies();
+
+        let new_delinquencies_parameters = new_delinquencies_parameters_arc.lock().unwrap();
+        assert_eq!(
+            new_delinquencies_parameters[0].0,
+            then + Duration::from_secs(30 * 24 * 3600)
+        );
+    }
+
+    #[test]
+    fn scan_for_delinquencies_redacts_wallets_when_redaction_is_on() {
+        init_test_logging();
+        let newly_banned = make_receivable_account(9876, true);
+        let receivable_dao = ReceivableDaoMock::new()
+            .new_delinquencies_result(vec![newly_banned.clone()])
+            .paid_delinquencies_result(vec![]);
+        let banned_dao = BannedDaoMock::new().ban_list_result(vec![]);
+        let mut subject = make_subject(None, None, Some(receivable_dao), Some(banned_dao), None);
+        subject.dunned.insert(
+            newly_banned.wallet,
+            SystemTime::now() - DUNNING_PERIOD - Duration::from_secs(1),
+        );
+        redacted::set_redaction(true);
+
+        subject.scan_for_delinquencies();
+
+        redacted::set_redaction(false);
+        TestLogHandler::new().exists_log_matching(
+            "INFO: Accountant: Wallet <redacted> \\(balance: 9876 MASQ, age: \\d+ sec\\) banned for delinquency",
+        );
+    }
+
     #[test]
     fn report_routing_service_provided_message_is_received() {
         init_test_logging();
@@ -2656,6 +4086,45 @@ pub mod tests {
         ));
     }
 
+    #[test]
+    fn report_earning_wallet_change_message_moves_debt_to_the_new_wallet() {
+        init_test_logging();
+        let transfer_balance_parameters_arc = Arc::new(Mutex::new(vec![]));
+        let payable_dao_mock = PayableDaoMock::new()
+            .transfer_balance_parameters(&transfer_balance_parameters_arc)
+            .transfer_balance_result(Some(1234))
+            .transfer_balance_result(None);
+        let mut subject = make_subject(None, Some(payable_dao_mock), None, None, None);
+        let previous_wallet = make_wallet("previous");
+        let current_wallet = make_wallet("current");
+        let msg = ReportEarningWalletChangeMessage {
+            previous_wallet: previous_wallet.clone(),
+            current_wallet: current_wallet.clone(),
+            effective_block: 4321,
+        };
+
+        subject.handle_report_earning_wallet_change_message(msg.clone());
+        subject.handle_report_earning_wallet_change_message(msg);
+
+        let transfer_balance_parameters = transfer_balance_parameters_arc.lock().unwrap();
+        assert_eq!(
+            *transfer_balance_parameters,
+            vec![
+                (previous_wallet.clone(), current_wallet.clone()),
+                (previous_wallet.clone(), current_wallet.clone())
+            ]
+        );
+        let tlh = TestLogHandler::new();
+        tlh.exists_log_containing(&format!(
+            "INFO: Accountant: Moved debt of 1234 from wallet {} to wallet {}, which replaces it as of block 4321",
+            previous_wallet, current_wallet
+        ));
+        tlh.exists_log_containing(&format!(
+            "DEBUG: Accountant: No debt to move from wallet {} to wallet {}",
+            previous_wallet, current_wallet
+        ));
+    }
+
     #[test]
     fn report_exit_service_consumed_message_is_received_for_our_consuming_wallet() {
         init_test_logging();
@@ -2822,6 +4291,95 @@ pub mod tests {
         ));
     }
 
+    #[test]
+    fn handle_sent_payments_settles_them_in_the_payment_journal() {
+        let payment = Payment::new(make_wallet("booga"), 1234, H256::from_uint(&U256::from(1)));
+        let record_settled_params_arc = Arc::new(Mutex::new(vec![]));
+        let mut subject = make_subject(
+            None,
+            Some(PayableDaoMock::new().payment_sent_result(Ok(()))),
+            None,
+            None,
+            None,
+        );
+        subject.payment_journal =
+            Box::new(PaymentJournalMock::new().record_settled_params(&record_settled_params_arc));
+
+        subject.handle_sent_payments(SentPayments {
+            payments: vec![Ok(payment.clone())],
+        });
+
+        let record_settled_params = record_settled_params_arc.lock().unwrap();
+        assert_eq!(*record_settled_params, vec![payment]);
+    }
+
+    #[test]
+    fn recover_payments_records_unsettled_payments_the_payable_has_not_seen() {
+        init_test_logging();
+        let payment = Payment::new(make_wallet("booga"), 1234, H256::from_uint(&U256::from(1)));
+        let account_status_parameters_arc = Arc::new(Mutex::new(vec![]));
+        let payment_sent_parameters_arc = Arc::new(Mutex::new(vec![]));
+        let record_settled_params_arc = Arc::new(Mutex::new(vec![]));
+        let payable_dao = PayableDaoMock::new()
+            .account_status_parameters(&account_status_parameters_arc)
+            .account_status_result(Some(PayableAccount {
+                wallet: make_wallet("booga"),
+                balance: 1234,
+                last_paid_timestamp: from_time_t(0),
+                pending_payment_transaction: None,
+            }))
+            .payment_sent_parameters(payment_sent_parameters_arc.clone())
+            .payment_sent_result(Ok(()));
+        let mut subject = make_subject(None, Some(payable_dao), None, None, None);
+        subject.payment_journal = Box::new(
+            PaymentJournalMock::new()
+                .unsettled_result(vec![payment.clone()])
+                .record_settled_params(&record_settled_params_arc),
+        );
+
+        subject.recover_payments();
+
+        let account_status_parameters = account_status_parameters_arc.lock().unwrap();
+        assert_eq!(*account_status_parameters, vec![make_wallet("booga")]);
+        let payment_sent_parameters = payment_sent_parameters_arc.lock().unwrap();
+        assert_eq!(*payment_sent_parameters, vec![payment.clone()]);
+        let record_settled_params = record_settled_params_arc.lock().unwrap();
+        assert_eq!(*record_settled_params, vec![payment.clone()]);
+        TestLogHandler::new().exists_log_containing(&format!(
+            "WARN: Accountant: Recovering payment of 1234 sent to {} (transaction {}) before the Node went down",
+            make_wallet("booga"),
+            payment.transaction
+        ));
+    }
+
+    #[test]
+    fn recover_payments_only_settles_unsettled_payments_the_payable_already_shows() {
+        let payment = Payment::new(make_wallet("booga"), 1234, H256::from_uint(&U256::from(1)));
+        let payment_sent_parameters_arc = Arc::new(Mutex::new(vec![]));
+        let record_settled_params_arc = Arc::new(Mutex::new(vec![]));
+        let payable_dao = PayableDaoMock::new()
+            .account_status_result(Some(PayableAccount {
+                wallet: make_wallet("booga"),
+                balance: 0,
+                last_paid_timestamp: payment.timestamp,
+                pending_payment_transaction: Some(payment.transaction),
+            }))
+            .payment_sent_parameters(payment_sent_parameters_arc.clone());
+        let mut subject = make_subject(None, Some(payable_dao), None, None, None);
+        subject.payment_journal = Box::new(
+            PaymentJournalMock::new()
+                .unsettled_result(vec![payment.clone()])
+                .record_settled_params(&record_settled_params_arc),
+        );
+
+        subject.recover_payments();
+
+        let payment_sent_parameters = payment_sent_parameters_arc.lock().unwrap();
+        assert_eq!(payment_sent_parameters.is_empty(), true);
+        let record_settled_params = record_settled_params_arc.lock().unwrap();
+        assert_eq!(*record_settled_params, vec![payment]);
+    }
+
     #[test]
     fn jackass_unsigned_to_signed_handles_zero() {
         let result = jackass_unsigned_to_signed(0u64);
@@ -2884,8 +4442,14 @@ pub mod tests {
             Box::new(payable_dao_factory),
             Box::new(receivable_dao_factory),
             Box::new(banned_dao_factory),
+            Box::new(UsageHistoryDaoFactoryMock::new(
+                UsageHistoryDaoMock::default(),
+            )),
+            Box::new(TrustDaoFactoryMock::new(TrustDaoMock::default())),
+            Box::new(FinancialsDaoFactoryMock::new(FinancialsDaoMock::default())),
             Box::new(ConfigDaoFactoryMock::new(ConfigDaoMock::new())),
         );
+        subject.payment_journal = Box::new(PaymentJournalMock::new());
         subject.persistent_configuration = if let Some(persistent_config) = persistent_config_opt {
             Box::new(persistent_config)
         } else {
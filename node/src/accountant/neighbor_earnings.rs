@@ -0,0 +1,96 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use crate::sub_lib::wallet::Wallet;
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+lazy_static! {
+    pub static ref EARNINGS_CACHE: EarningsCache = EarningsCache::default();
+}
+
+// What one wallet has brought in since the Node started: what it has been charged for the traffic
+// this Node carried for it, and what it has actually paid
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Earnings {
+    // Paid comes first, so that a wallet that pays always outranks one that only runs up a bill
+    pub paid_gwei: u64,
+    pub charged_gwei: u64,
+}
+
+// Written by the Accountant and read by the Neighborhood, which knows its neighbors by their
+// earning wallets: a neighbor only gets credit here if it pays from the wallet it earns into.
+#[derive(Default)]
+pub struct EarningsCache {
+    cache: RwLock<HashMap<Wallet, Earnings>>,
+}
+
+impl EarningsCache {
+    pub fn add_charge(&self, wallet: &Wallet, gwei: u64) {
+        self.update(wallet, |earnings| {
+            earnings.charged_gwei = earnings.charged_gwei.saturating_add(gwei)
+        })
+    }
+
+    pub fn add_payment(&self, wallet: &Wallet, gwei: u64) {
+        self.update(wallet, |earnings| {
+            earnings.paid_gwei = earnings.paid_gwei.saturating_add(gwei)
+        })
+    }
+
+    pub fn earnings(&self, wallet: &Wallet) -> Earnings {
+        self.cache
+            .read()
+            .expect("Failed to read from earnings cache")
+            .get(&wallet.as_address_wallet())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn update<F: FnOnce(&mut Earnings)>(&self, wallet: &Wallet, f: F) {
+        f(self
+            .cache
+            .write()
+            .expect("Failed to update earnings cache")
+            .entry(wallet.as_address_wallet())
+            .or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::make_wallet;
+
+    #[test]
+    fn charges_and_payments_add_up_per_wallet() {
+        let subject = EarningsCache::default();
+        let payer = make_wallet("payer");
+        let debtor = make_wallet("debtor");
+
+        subject.add_charge(&payer, 100);
+        subject.add_payment(&payer, 60);
+        subject.add_payment(&payer, 40);
+        subject.add_charge(&debtor, 1000);
+        subject.add_charge(&debtor, u64::MAX);
+
+        assert_eq!(
+            subject.earnings(&payer),
+            Earnings {
+                paid_gwei: 100,
+                charged_gwei: 100
+            }
+        );
+        assert_eq!(
+            subject.earnings(&debtor),
+            Earnings {
+                paid_gwei: 0,
+                charged_gwei: u64::MAX
+            }
+        );
+        assert_eq!(
+            subject.earnings(&make_wallet("stranger")),
+            Earnings::default()
+        );
+        assert!(subject.earnings(&payer) > subject.earnings(&debtor));
+    }
+}
@@ -3,11 +3,13 @@ use crate::accountant::{jackass_unsigned_to_signed, PaymentError};
 use crate::database::connection_wrapper::ConnectionWrapper;
 use crate::database::dao_utils;
 use crate::database::dao_utils::DaoFactoryReal;
+use crate::sub_lib::clock::{Clock, ClockReal};
 use crate::sub_lib::wallet::Wallet;
 use rusqlite::types::{ToSql, Type};
-use rusqlite::{Error, OptionalExtension, NO_PARAMS};
+use rusqlite::{Error, OptionalExtension, Statement, NO_PARAMS};
 use serde_json::{self, json};
 use std::fmt::Debug;
+use std::sync::Arc;
 use std::time::SystemTime;
 use web3::types::H256;
 
@@ -55,9 +57,7 @@ pub trait PayableDao: Debug + Send {
 
     fn non_pending_payables(&self) -> Vec<PayableAccount>;
 
-    fn top_records(&self, minimum_amount: u64, maximum_age: u64) -> Vec<PayableAccount>;
-
-    fn total(&self) -> u64;
+    fn transfer_balance(&self, from: &Wallet, to: &Wallet) -> Option<i64>;
 }
 
 pub trait PayableDaoFactory {
@@ -66,13 +66,16 @@ pub trait PayableDaoFactory {
 
 impl PayableDaoFactory for DaoFactoryReal {
     fn make(&self) -> Box<dyn PayableDao> {
-        Box::new(PayableDaoReal::new(self.make_connection()))
+        let mut dao = PayableDaoReal::new(self.make_connection());
+        dao.clock = self.clock.clone();
+        Box::new(dao)
     }
 }
 
 #[derive(Debug)]
 pub struct PayableDaoReal {
     conn: Box<dyn ConnectionWrapper>,
+    clock: Arc<dyn Clock>,
 }
 
 impl PayableDao for PayableDaoReal {
@@ -170,114 +173,45 @@ impl PayableDao for PayableDaoReal {
         .collect()
     }
 
-    fn top_records(&self, minimum_amount: u64, maximum_age: u64) -> Vec<PayableAccount> {
-        let min_amt = match jackass_unsigned_to_signed(minimum_amount) {
-            Ok(n) => n,
-            Err(_) => 0x7FFF_FFFF_FFFF_FFFF,
-        };
-        let max_age = match jackass_unsigned_to_signed(maximum_age) {
-            Ok(n) => n,
-            Err(_) => 0x7FFF_FFFF_FFFF_FFFF,
+    // An account with a payment still pending stays where it is, so that the payment can be
+    // matched up with it when it's confirmed
+    fn transfer_balance(&self, from: &Wallet, to: &Wallet) -> Option<i64> {
+        let balance = match self.account_status(from) {
+            Some(account) if account.pending_payment_transaction.is_none() => account.balance,
+            _ => return None,
         };
-        let min_timestamp = dao_utils::now_time_t() - max_age;
-        let mut stmt = self
-            .conn
-            .prepare(
-                r#"
-                select
-                    balance,
-                    last_paid_timestamp,
-                    wallet_address,
-                    pending_payment_transaction
-                from
-                    payable
-                where
-                    balance >= ? and
-                    last_paid_timestamp >= ?
-                order by
-                    balance desc,
-                    last_paid_timestamp desc
-            "#,
-            )
-            .expect("Internal error");
-        let params: &[&dyn ToSql] = &[&min_amt, &min_timestamp];
-        stmt.query_map(params, |row| {
-            let balance_result = row.get(0);
-            let last_paid_timestamp_result = row.get(1);
-            let wallet_result: Result<Wallet, rusqlite::Error> = row.get(2);
-            let pending_payment_transaction_result: Result<Option<String>, Error> = row.get(3);
-            match (
-                balance_result,
-                last_paid_timestamp_result,
-                wallet_result,
-                pending_payment_transaction_result,
-            ) {
-                (
-                    Ok(balance),
-                    Ok(last_paid_timestamp),
-                    Ok(wallet),
-                    Ok(pending_payment_transaction),
-                ) => Ok(PayableAccount {
-                    wallet,
-                    balance,
-                    last_paid_timestamp: dao_utils::from_time_t(last_paid_timestamp),
-                    pending_payment_transaction: match pending_payment_transaction {
-                        Some(tx) => match serde_json::from_value(json!(tx)) {
-                            Ok(transaction) => Some(transaction),
-                            Err(e) => panic!("{:?}", e),
-                        },
-                        None => None,
-                    },
-                }),
-                _ => panic!("Database is corrupt: PAYABLE table columns and/or types"),
-            }
-        })
-        .expect("Database is corrupt")
-        .flatten()
-        .collect()
-    }
-
-    fn total(&self) -> u64 {
+        if let Err(e) = self.try_increase_balance(to, balance) {
+            panic!("Database is corrupt: {}", e)
+        }
         let mut stmt = self
             .conn
-            .prepare("select sum(balance) from payable")
+            .prepare("delete from payable where wallet_address = ?")
             .expect("Internal error");
-        match stmt.query_row(NO_PARAMS, |row| {
-            let total_balance_result: Result<i64, rusqlite::Error> = row.get(0);
-            match total_balance_result {
-                Ok(total_balance) => Ok(total_balance as u64),
-                Err(e)
-                    if e == rusqlite::Error::InvalidColumnType(
-                        0,
-                        "sum(balance)".to_string(),
-                        Type::Null,
-                    ) =>
-                {
-                    Ok(0u64)
-                }
-                Err(e) => panic!(
-                    "Database is corrupt: PAYABLE table columns and/or types: {:?}",
-                    e
-                ),
-            }
-        }) {
-            Ok(value) => value,
-            Err(e) => panic!("Database is corrupt: {:?}", e),
+        match stmt.execute(&[&from]) {
+            Ok(_) => Some(balance),
+            Err(e) => panic!("Database is corrupt: {}", e),
         }
     }
 }
 
 impl PayableDaoReal {
     pub fn new(conn: Box<dyn ConnectionWrapper>) -> PayableDaoReal {
-        PayableDaoReal { conn }
+        PayableDaoReal {
+            conn,
+            clock: Arc::new(ClockReal {}),
+        }
     }
 
     fn try_increase_balance(&self, wallet: &Wallet, amount: i64) -> Result<bool, String> {
         let mut stmt = self
             .conn
-            .prepare("insert into payable (wallet_address, balance, last_paid_timestamp, pending_payment_transaction) values (:address, :balance, strftime('%s','now'), null) on conflict (wallet_address) do update set balance = balance + :balance where wallet_address = :address")
+            .prepare("insert into payable (wallet_address, balance, last_paid_timestamp, pending_payment_transaction) values (:address, :balance, :now, null) on conflict (wallet_address) do update set balance = balance + :balance where wallet_address = :address")
             .expect("Internal error");
-        let params: &[(&str, &dyn ToSql)] = &[(":address", &wallet), (":balance", &amount)];
+        let params: &[(&str, &dyn ToSql)] = &[
+            (":address", &wallet),
+            (":balance", &amount),
+            (":now", &dao_utils::to_time_t(self.clock.now())),
+        ];
         match stmt.execute_named(params) {
             Ok(0) => Ok(false),
             Ok(_) => Ok(true),
@@ -310,6 +244,101 @@ impl PayableDaoReal {
     }
 }
 
+// The top payables and their total, read by FinancialsDao inside a transaction so that they come
+// from the same snapshot as the receivables
+pub fn top_records<'a>(
+    prepare: impl FnOnce(&str) -> Result<Statement<'a>, Error>,
+    now: SystemTime,
+    minimum_amount: u64,
+    maximum_age: u64,
+) -> Vec<PayableAccount> {
+    let min_amt = match jackass_unsigned_to_signed(minimum_amount) {
+        Ok(n) => n,
+        Err(_) => 0x7FFF_FFFF_FFFF_FFFF,
+    };
+    let max_age = match jackass_unsigned_to_signed(maximum_age) {
+        Ok(n) => n,
+        Err(_) => 0x7FFF_FFFF_FFFF_FFFF,
+    };
+    let min_timestamp = dao_utils::to_time_t(now) - max_age;
+    let mut stmt = prepare(
+        r#"
+            select
+                balance,
+                last_paid_timestamp,
+                wallet_address,
+                pending_payment_transaction
+            from
+                payable
+            where
+                balance >= ? and
+                last_paid_timestamp >= ?
+            order by
+                balance desc,
+                last_paid_timestamp desc
+        "#,
+    )
+    .expect("Internal error");
+    let params: &[&dyn ToSql] = &[&min_amt, &min_timestamp];
+    stmt.query_map(params, |row| {
+        let balance_result = row.get(0);
+        let last_paid_timestamp_result = row.get(1);
+        let wallet_result: Result<Wallet, rusqlite::Error> = row.get(2);
+        let pending_payment_transaction_result: Result<Option<String>, Error> = row.get(3);
+        match (
+            balance_result,
+            last_paid_timestamp_result,
+            wallet_result,
+            pending_payment_transaction_result,
+        ) {
+            (Ok(balance), Ok(last_paid_timestamp), Ok(wallet), Ok(pending_payment_transaction)) => {
+                Ok(PayableAccount {
+                    wallet,
+                    balance,
+                    last_paid_timestamp: dao_utils::from_time_t(last_paid_timestamp),
+                    pending_payment_transaction: match pending_payment_transaction {
+                        Some(tx) => match serde_json::from_value(json!(tx)) {
+                            Ok(transaction) => Some(transaction),
+                            Err(e) => panic!("{:?}", e),
+                        },
+                        None => None,
+                    },
+                })
+            }
+            _ => panic!("Database is corrupt: PAYABLE table columns and/or types"),
+        }
+    })
+    .expect("Database is corrupt")
+    .flatten()
+    .collect()
+}
+
+pub fn total<'a>(prepare: impl FnOnce(&str) -> Result<Statement<'a>, Error>) -> u64 {
+    let mut stmt = prepare("select sum(balance) from payable").expect("Internal error");
+    match stmt.query_row(NO_PARAMS, |row| {
+        let total_balance_result: Result<i64, rusqlite::Error> = row.get(0);
+        match total_balance_result {
+            Ok(total_balance) => Ok(total_balance as u64),
+            Err(e)
+                if e == rusqlite::Error::InvalidColumnType(
+                    0,
+                    "sum(balance)".to_string(),
+                    Type::Null,
+                ) =>
+            {
+                Ok(0u64)
+            }
+            Err(e) => panic!(
+                "Database is corrupt: PAYABLE table columns and/or types: {:?}",
+                e
+            ),
+        }
+    }) {
+        Ok(value) => value,
+        Err(e) => panic!("Database is corrupt: {:?}", e),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -317,10 +346,12 @@ mod tests {
     use crate::database::db_initializer;
     use crate::database::db_initializer::{DbInitializer, DbInitializerReal};
     use crate::test_utils::make_wallet;
+    use crate::test_utils::virtual_clock::VirtualClock;
     use ethereum_types::BigEndianHash;
     use masq_lib::test_utils::utils::{ensure_node_home_directory_exists, DEFAULT_CHAIN_ID};
     use rusqlite::{Connection, OpenFlags, NO_PARAMS};
     use std::str::FromStr;
+    use std::time::Duration;
     use web3::types::U256;
 
     #[test]
@@ -360,6 +391,24 @@ mod tests {
         );
     }
 
+    #[test]
+    fn more_money_payable_stamps_new_accounts_with_the_time_on_the_factorys_clock() {
+        let home_dir = ensure_node_home_directory_exists(
+            "payable_dao",
+            "more_money_payable_stamps_new_accounts_with_the_time_on_the_factorys_clock",
+        );
+        let then = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        let mut factory = DaoFactoryReal::new(&home_dir, DEFAULT_CHAIN_ID, true);
+        factory.clock = Arc::new(VirtualClock::new(then));
+        let wallet = make_wallet("booga");
+        let subject = PayableDaoFactory::make(&factory);
+
+        subject.more_money_payable(&wallet, 1234).unwrap();
+
+        let status = subject.account_status(&wallet).unwrap();
+        assert_eq!(status.last_paid_timestamp, then);
+    }
+
     #[test]
     fn more_money_payable_works_for_existing_address() {
         let home_dir = ensure_node_home_directory_exists(
@@ -700,10 +749,13 @@ mod tests {
             Some("0x1111111122222222333333334444444455555555666666667777777788888888"),
         );
 
-        let subject = PayableDaoReal::new(conn);
-
-        let top_records = subject.top_records(1_000_000_000, 86400);
-        let total = subject.total();
+        let top_records = top_records(
+            |sql| conn.prepare(sql),
+            SystemTime::now(),
+            1_000_000_000,
+            86400,
+        );
+        let total = total(|sql| conn.prepare(sql));
 
         assert_eq!(
             top_records,
@@ -737,10 +789,63 @@ mod tests {
         let conn = DbInitializerReal::new()
             .initialize(&home_dir, DEFAULT_CHAIN_ID, true)
             .unwrap();
-        let subject = PayableDaoReal::new(conn);
 
-        let result = subject.total();
+        let result = total(|sql| conn.prepare(sql));
 
         assert_eq!(result, 0)
     }
+
+    #[test]
+    fn transfer_balance_moves_the_balance_onto_the_new_wallet() {
+        let home_dir = ensure_node_home_directory_exists(
+            "payable_dao",
+            "transfer_balance_moves_the_balance_onto_the_new_wallet",
+        );
+        let subject = PayableDaoReal::new(
+            DbInitializerReal::new()
+                .initialize(&home_dir, DEFAULT_CHAIN_ID, true)
+                .unwrap(),
+        );
+        let old_wallet = make_wallet("old");
+        let new_wallet = make_wallet("new");
+        subject.more_money_payable(&old_wallet, 1234).unwrap();
+        subject.more_money_payable(&new_wallet, 4321).unwrap();
+
+        let result = subject.transfer_balance(&old_wallet, &new_wallet);
+
+        assert_eq!(result, Some(1234));
+        assert_eq!(subject.account_status(&old_wallet), None);
+        assert_eq!(subject.account_status(&new_wallet).unwrap().balance, 5555);
+        assert_eq!(subject.transfer_balance(&old_wallet, &new_wallet), None);
+        assert_eq!(subject.account_status(&new_wallet).unwrap().balance, 5555);
+    }
+
+    #[test]
+    fn transfer_balance_leaves_an_account_with_a_pending_payment_alone() {
+        let home_dir = ensure_node_home_directory_exists(
+            "payable_dao",
+            "transfer_balance_leaves_an_account_with_a_pending_payment_alone",
+        );
+        let subject = PayableDaoReal::new(
+            DbInitializerReal::new()
+                .initialize(&home_dir, DEFAULT_CHAIN_ID, true)
+                .unwrap(),
+        );
+        let old_wallet = make_wallet("old");
+        let new_wallet = make_wallet("new");
+        subject.more_money_payable(&old_wallet, 1234).unwrap();
+        subject
+            .payment_sent(&Payment::new(
+                old_wallet.clone(),
+                1000,
+                H256::from_uint(&U256::from(1)),
+            ))
+            .unwrap();
+
+        let result = subject.transfer_balance(&old_wallet, &new_wallet);
+
+        assert_eq!(result, None);
+        assert_eq!(subject.account_status(&old_wallet).unwrap().balance, 234);
+        assert_eq!(subject.account_status(&new_wallet), None);
+    }
 }
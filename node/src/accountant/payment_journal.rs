@@ -0,0 +1,223 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use crate::accountant::payable_dao::Payment;
+use crate::database::dao_utils::{from_time_t, to_time_t};
+use crate::sub_lib::wallet::Wallet;
+use ethereum_types::H256;
+use lazy_static::lazy_static;
+use std::fs::{File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
+
+pub const PAYMENT_JOURNAL_FILE: &str = "payment_journal.log";
+
+lazy_static! {
+    // The BlockchainBridge appends and the Accountant compacts, each on its own thread
+    static ref JOURNAL_LOCK: Mutex<()> = Mutex::new(());
+}
+
+// Between the moment the BlockchainBridge sends a payment and the moment the Accountant writes it
+// off the payable, the payment exists nowhere but in a message; if the Node dies then, the next
+// scan pays the same debt again. The BlockchainBridge writes each payment here, and syncs it to
+// disk, before it tells anybody about it; the Accountant settles it once the payable says it's
+// paid, and on startup replays whatever is still unsettled.
+pub trait PaymentJournal: Send {
+    fn record_sent(&self, payment: &Payment);
+    fn record_settled(&self, payment: &Payment);
+    fn unsettled(&self) -> Vec<Payment>;
+}
+
+pub struct PaymentJournalReal {
+    path: PathBuf,
+}
+
+impl PaymentJournal for PaymentJournalReal {
+    fn record_sent(&self, payment: &Payment) {
+        let _guard = JOURNAL_LOCK.lock().expect("Payment journal is poisoned");
+        self.append(&format!(
+            "sent {:x} {} {} {}",
+            payment.transaction,
+            payment.to,
+            payment.amount,
+            to_time_t(payment.timestamp)
+        ));
+    }
+
+    // Once nothing is left unsettled, the journal starts over empty, so it never grows for long
+    fn record_settled(&self, payment: &Payment) {
+        let _guard = JOURNAL_LOCK.lock().expect("Payment journal is poisoned");
+        let unsettled = self.read_unsettled();
+        if !unsettled
+            .iter()
+            .any(|sent| sent.transaction == payment.transaction)
+        {
+            return;
+        }
+        if unsettled.len() == 1 {
+            File::create(&self.path)
+                .unwrap_or_else(|e| panic!("Can't empty payment journal {:?}: {}", self.path, e));
+        } else {
+            self.append(&format!("settled {:x}", payment.transaction));
+        }
+    }
+
+    fn unsettled(&self) -> Vec<Payment> {
+        let _guard = JOURNAL_LOCK.lock().expect("Payment journal is poisoned");
+        self.read_unsettled()
+    }
+}
+
+impl PaymentJournalReal {
+    pub fn new(data_directory: &Path) -> PaymentJournalReal {
+        PaymentJournalReal {
+            path: data_directory.join(PAYMENT_JOURNAL_FILE),
+        }
+    }
+
+    fn append(&self, line: &str) {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .unwrap_or_else(|e| panic!("Can't open payment journal {:?}: {}", self.path, e));
+        file.write_all(format!("{}\n", line).as_bytes())
+            .and_then(|_| file.sync_data())
+            .unwrap_or_else(|e| panic!("Can't write payment journal {:?}: {}", self.path, e));
+    }
+
+    // A line cut short by a crash can only be the last one, and it was never acted upon
+    fn read_unsettled(&self) -> Vec<Payment> {
+        let contents = match std::fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(ref e) if e.kind() == ErrorKind::NotFound => return vec![],
+            Err(e) => panic!("Can't read payment journal {:?}: {}", self.path, e),
+        };
+        let mut unsettled: Vec<Payment> = vec![];
+        contents.lines().for_each(|line| {
+            let fields = line.split(' ').collect::<Vec<&str>>();
+            match fields.as_slice() {
+                ["sent", transaction, wallet, amount, timestamp] => {
+                    if let (Ok(transaction), Ok(to), Ok(amount), Ok(timestamp)) = (
+                        H256::from_str(transaction),
+                        Wallet::from_str(wallet),
+                        amount.parse::<u64>(),
+                        timestamp.parse::<i64>(),
+                    ) {
+                        unsettled.push(Payment {
+                            to,
+                            amount,
+                            timestamp: from_time_t(timestamp),
+                            transaction,
+                        })
+                    }
+                }
+                ["settled", transaction] => {
+                    if let Ok(transaction) = H256::from_str(transaction) {
+                        unsettled.retain(|payment| payment.transaction != transaction)
+                    }
+                }
+                _ => (),
+            }
+        });
+        unsettled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::make_wallet;
+    use masq_lib::test_utils::utils::ensure_node_home_directory_exists;
+    use std::time::SystemTime;
+
+    fn make_payment(wallet: &str, amount: u64, transaction: u64) -> Payment {
+        Payment {
+            to: make_wallet(wallet),
+            amount,
+            timestamp: from_time_t(to_time_t(SystemTime::now())),
+            transaction: H256::from_low_u64_be(transaction),
+        }
+    }
+
+    #[test]
+    fn sent_payments_stay_unsettled_until_settled() {
+        let data_dir = ensure_node_home_directory_exists(
+            "payment_journal",
+            "sent_payments_stay_unsettled_until_settled",
+        );
+        let subject = PaymentJournalReal::new(&data_dir);
+        let first = make_payment("first", 1000, 1);
+        let second = make_payment("second", 2000, 2);
+        subject.record_sent(&first);
+        subject.record_sent(&second);
+
+        subject.record_settled(&first);
+
+        assert_eq!(subject.unsettled(), vec![second]);
+    }
+
+    #[test]
+    fn the_journal_survives_being_reopened() {
+        let data_dir = ensure_node_home_directory_exists(
+            "payment_journal",
+            "the_journal_survives_being_reopened",
+        );
+        let payment = make_payment("wallet", 1000, 1);
+        PaymentJournalReal::new(&data_dir).record_sent(&payment);
+
+        let result = PaymentJournalReal::new(&data_dir).unsettled();
+
+        assert_eq!(result, vec![payment]);
+    }
+
+    #[test]
+    fn the_journal_empties_once_everything_is_settled() {
+        let data_dir = ensure_node_home_directory_exists(
+            "payment_journal",
+            "the_journal_empties_once_everything_is_settled",
+        );
+        let subject = PaymentJournalReal::new(&data_dir);
+        let payment = make_payment("wallet", 1000, 1);
+        subject.record_sent(&payment);
+
+        subject.record_settled(&payment);
+
+        assert_eq!(subject.unsettled(), vec![]);
+        let contents = std::fs::read_to_string(data_dir.join(PAYMENT_JOURNAL_FILE)).unwrap();
+        assert_eq!(contents, "");
+    }
+
+    #[test]
+    fn settling_a_payment_that_was_never_journaled_writes_nothing() {
+        let data_dir = ensure_node_home_directory_exists(
+            "payment_journal",
+            "settling_a_payment_that_was_never_journaled_writes_nothing",
+        );
+        let subject = PaymentJournalReal::new(&data_dir);
+
+        subject.record_settled(&make_payment("wallet", 1000, 1));
+
+        assert_eq!(data_dir.join(PAYMENT_JOURNAL_FILE).exists(), false);
+    }
+
+    #[test]
+    fn a_line_cut_short_by_a_crash_is_ignored() {
+        let data_dir = ensure_node_home_directory_exists(
+            "payment_journal",
+            "a_line_cut_short_by_a_crash_is_ignored",
+        );
+        let subject = PaymentJournalReal::new(&data_dir);
+        let payment = make_payment("wallet", 1000, 1);
+        subject.record_sent(&payment);
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(data_dir.join(PAYMENT_JOURNAL_FILE))
+            .unwrap();
+        file.write_all(b"sent 00000000000000").unwrap();
+
+        let result = subject.unsettled();
+
+        assert_eq!(result, vec![payment]);
+    }
+}
@@ -6,12 +6,14 @@ use crate::database::dao_utils;
 use crate::database::dao_utils::{to_time_t, DaoFactoryReal};
 use crate::db_config::config_dao::{ConfigDaoWrite, ConfigDaoWriteableReal};
 use crate::db_config::persistent_configuration::PersistentConfigError;
+use crate::sub_lib::clock::{Clock, ClockReal};
 use crate::sub_lib::logger::Logger;
 use crate::sub_lib::wallet::Wallet;
 use indoc::indoc;
 use rusqlite::named_params;
 use rusqlite::types::{ToSql, Type};
-use rusqlite::{OptionalExtension, Row, NO_PARAMS};
+use rusqlite::{Error, OptionalExtension, Row, Statement, NO_PARAMS};
+use std::sync::Arc;
 use std::time::SystemTime;
 
 #[derive(Debug, PartialEq)]
@@ -55,10 +57,6 @@ pub trait ReceivableDao: Send {
     ) -> Vec<ReceivableAccount>;
 
     fn paid_delinquencies(&self, payment_curves: &PaymentCurves) -> Vec<ReceivableAccount>;
-
-    fn top_records(&self, minimum_amount: u64, maximum_age: u64) -> Vec<ReceivableAccount>;
-
-    fn total(&self) -> u64;
 }
 
 pub trait ReceivableDaoFactory {
@@ -67,12 +65,15 @@ pub trait ReceivableDaoFactory {
 
 impl ReceivableDaoFactory for DaoFactoryReal {
     fn make(&self) -> Box<dyn ReceivableDao> {
-        Box::new(ReceivableDaoReal::new(self.make_connection()))
+        let mut dao = ReceivableDaoReal::new(self.make_connection());
+        dao.clock = self.clock.clone();
+        Box::new(dao)
     }
 }
 
 pub struct ReceivableDaoReal {
     conn: Box<dyn ConnectionWrapper>,
+    clock: Arc<dyn Clock>,
     logger: Logger,
 }
 
@@ -207,89 +208,13 @@ impl ReceivableDao for ReceivableDaoReal {
         .flatten()
         .collect()
     }
-
-    fn top_records(&self, minimum_amount: u64, maximum_age: u64) -> Vec<ReceivableAccount> {
-        let min_amt = match jackass_unsigned_to_signed(minimum_amount) {
-            Ok(n) => n,
-            Err(_) => 0x7FFF_FFFF_FFFF_FFFF,
-        };
-        let max_age = match jackass_unsigned_to_signed(maximum_age) {
-            Ok(n) => n,
-            Err(_) => 0x7FFF_FFFF_FFFF_FFFF,
-        };
-        let min_timestamp = dao_utils::now_time_t() - max_age;
-        let mut stmt = self
-            .conn
-            .prepare(
-                r#"
-                select
-                    balance,
-                    last_received_timestamp,
-                    wallet_address
-                from
-                    receivable
-                where
-                    balance >= ? and
-                    last_received_timestamp >= ?
-                order by
-                    balance desc,
-                    last_received_timestamp desc
-            "#,
-            )
-            .expect("Internal error");
-        let params: &[&dyn ToSql] = &[&min_amt, &min_timestamp];
-        stmt.query_map(params, |row| {
-            let balance_result = row.get(0);
-            let last_paid_timestamp_result = row.get(1);
-            let wallet_result: Result<Wallet, rusqlite::Error> = row.get(2);
-            match (balance_result, last_paid_timestamp_result, wallet_result) {
-                (Ok(balance), Ok(last_paid_timestamp), Ok(wallet)) => Ok(ReceivableAccount {
-                    wallet,
-                    balance,
-                    last_received_timestamp: dao_utils::from_time_t(last_paid_timestamp),
-                }),
-                _ => panic!("Database is corrupt: RECEIVABLE table columns and/or types"),
-            }
-        })
-        .expect("Database is corrupt")
-        .flatten()
-        .collect()
-    }
-
-    fn total(&self) -> u64 {
-        let mut stmt = self
-            .conn
-            .prepare("select sum(balance) from receivable")
-            .expect("Internal error");
-        match stmt.query_row(NO_PARAMS, |row| {
-            let total_balance_result: Result<i64, rusqlite::Error> = row.get(0);
-            match total_balance_result {
-                Ok(total_balance) => Ok(total_balance as u64),
-                Err(e)
-                    if e == rusqlite::Error::InvalidColumnType(
-                        0,
-                        "sum(balance)".to_string(),
-                        Type::Null,
-                    ) =>
-                {
-                    Ok(0u64)
-                }
-                Err(e) => panic!(
-                    "Database is corrupt: RECEIVABLE table columns and/or types: {:?}",
-                    e
-                ),
-            }
-        }) {
-            Ok(value) => value,
-            Err(e) => panic!("Database is corrupt: {:?}", e),
-        }
-    }
 }
 
 impl ReceivableDaoReal {
     pub fn new(conn: Box<dyn ConnectionWrapper>) -> ReceivableDaoReal {
         ReceivableDaoReal {
             conn,
+            clock: Arc::new(ClockReal {}),
             logger: Logger::new("ReceivableDaoReal"),
         }
     }
@@ -308,7 +233,7 @@ impl ReceivableDaoReal {
     }
 
     fn try_insert(&self, wallet: &Wallet, amount: i64) -> Result<(), String> {
-        let timestamp = dao_utils::to_time_t(SystemTime::now());
+        let timestamp = to_time_t(self.clock.now());
         let mut stmt = self.conn.prepare("insert into receivable (wallet_address, balance, last_received_timestamp) values (?, ?, ?)").expect("Internal error");
         let params: &[&dyn ToSql] = &[&wallet, &amount, &(timestamp as i64)];
         match stmt.execute(params) {
@@ -345,7 +270,7 @@ impl ReceivableDaoReal {
             let mut stmt = tx.prepare("update receivable set balance = balance - ?, last_received_timestamp = ? where wallet_address = ?")
                 .expect ("Internal SQL error");
             for transaction in payments {
-                let timestamp = dao_utils::now_time_t();
+                let timestamp = to_time_t(self.clock.now());
                 let gwei_amount = match jackass_unsigned_to_signed(transaction.gwei_amount) {
                     Ok(amount) => amount,
                     Err(e) => {
@@ -381,6 +306,84 @@ impl ReceivableDaoReal {
     }
 }
 
+// The top receivables and their total; see payable_dao
+pub fn top_records<'a>(
+    prepare: impl FnOnce(&str) -> Result<Statement<'a>, Error>,
+    now: SystemTime,
+    minimum_amount: u64,
+    maximum_age: u64,
+) -> Vec<ReceivableAccount> {
+    let min_amt = match jackass_unsigned_to_signed(minimum_amount) {
+        Ok(n) => n,
+        Err(_) => 0x7FFF_FFFF_FFFF_FFFF,
+    };
+    let max_age = match jackass_unsigned_to_signed(maximum_age) {
+        Ok(n) => n,
+        Err(_) => 0x7FFF_FFFF_FFFF_FFFF,
+    };
+    let min_timestamp = to_time_t(now) - max_age;
+    let mut stmt = prepare(
+        r#"
+            select
+                balance,
+                last_received_timestamp,
+                wallet_address
+            from
+                receivable
+            where
+                balance >= ? and
+                last_received_timestamp >= ?
+            order by
+                balance desc,
+                last_received_timestamp desc
+        "#,
+    )
+    .expect("Internal error");
+    let params: &[&dyn ToSql] = &[&min_amt, &min_timestamp];
+    stmt.query_map(params, |row| {
+        let balance_result = row.get(0);
+        let last_paid_timestamp_result = row.get(1);
+        let wallet_result: Result<Wallet, rusqlite::Error> = row.get(2);
+        match (balance_result, last_paid_timestamp_result, wallet_result) {
+            (Ok(balance), Ok(last_paid_timestamp), Ok(wallet)) => Ok(ReceivableAccount {
+                wallet,
+                balance,
+                last_received_timestamp: dao_utils::from_time_t(last_paid_timestamp),
+            }),
+            _ => panic!("Database is corrupt: RECEIVABLE table columns and/or types"),
+        }
+    })
+    .expect("Database is corrupt")
+    .flatten()
+    .collect()
+}
+
+pub fn total<'a>(prepare: impl FnOnce(&str) -> Result<Statement<'a>, Error>) -> u64 {
+    let mut stmt = prepare("select sum(balance) from receivable").expect("Internal error");
+    match stmt.query_row(NO_PARAMS, |row| {
+        let total_balance_result: Result<i64, rusqlite::Error> = row.get(0);
+        match total_balance_result {
+            Ok(total_balance) => Ok(total_balance as u64),
+            Err(e)
+                if e == rusqlite::Error::InvalidColumnType(
+                    0,
+                    "sum(balance)".to_string(),
+                    Type::Null,
+                ) =>
+            {
+                Ok(0u64)
+            }
+            Err(e) => panic!(
+                "Database is corrupt: RECEIVABLE table columns and/or types: {:?}",
+                e
+            ),
+        }
+    }) {
+        Ok(value) => value,
+        Err(e) => panic!("Database is corrupt: {:?}", e),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -396,10 +399,13 @@ mod tests {
     };
     use crate::test_utils::logging;
     use crate::test_utils::logging::TestLogHandler;
+    use crate::test_utils::virtual_clock::VirtualClock;
     use crate::test_utils::{assert_contains, make_wallet};
     use masq_lib::test_utils::utils::{ensure_node_home_directory_exists, DEFAULT_CHAIN_ID};
     use rusqlite::NO_PARAMS;
     use rusqlite::{Connection, Error, OpenFlags};
+    use std::time::Duration;
+    use web3::types::H256;
 
     #[test]
     fn conversion_from_pce_works() {
@@ -435,6 +441,8 @@ mod tests {
             block_number: 42u64,
             from: make_wallet("some_address"),
             gwei_amount: 18446744073709551615,
+            transaction_hash: H256::from([1; 32]),
+            confirmations: 1,
         }];
 
         let result = subject.try_multi_insert_payment(&payments);
@@ -466,6 +474,8 @@ mod tests {
             block_number: 42u64,
             from: make_wallet("some_address"),
             gwei_amount: 18446744073709551615,
+            transaction_hash: H256::from([2; 32]),
+            confirmations: 1,
         }];
 
         let result = subject.try_multi_insert_payment(&payments);
@@ -498,6 +508,8 @@ mod tests {
             block_number: 42u64,
             from: make_wallet("some_address"),
             gwei_amount: 18446744073709551615,
+            transaction_hash: H256::from([3; 32]),
+            confirmations: 1,
         }];
 
         let _ = subject.try_multi_insert_payment(&payments);
@@ -540,6 +552,24 @@ mod tests {
         );
     }
 
+    #[test]
+    fn more_money_receivable_stamps_new_accounts_with_the_time_on_the_factorys_clock() {
+        let home_dir = ensure_node_home_directory_exists(
+            "receivable_dao",
+            "more_money_receivable_stamps_new_accounts_with_the_time_on_the_factorys_clock",
+        );
+        let then = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        let mut factory = DaoFactoryReal::new(&home_dir, DEFAULT_CHAIN_ID, true);
+        factory.clock = Arc::new(VirtualClock::new(then));
+        let wallet = make_wallet("booga");
+        let subject = ReceivableDaoFactory::make(&factory);
+
+        subject.more_money_receivable(&wallet, 1234).unwrap();
+
+        let status = subject.account_status(&wallet).unwrap();
+        assert_eq!(status.last_received_timestamp, then);
+    }
+
     #[test]
     fn more_money_receivable_works_for_existing_address() {
         let home_dir = ensure_node_home_directory_exists(
@@ -622,11 +652,15 @@ mod tests {
                     from: debtor1.clone(),
                     gwei_amount: 1200u64,
                     block_number: 35u64,
+                    transaction_hash: H256::from([4; 32]),
+                    confirmations: 1,
                 },
                 Transaction {
                     from: debtor2.clone(),
                     gwei_amount: 2300u64,
                     block_number: 57u64,
+                    transaction_hash: H256::from([5; 32]),
+                    confirmations: 1,
                 },
             ];
 
@@ -677,6 +711,8 @@ mod tests {
                 from: debtor.clone(),
                 gwei_amount: 2300u64,
                 block_number: 33u64,
+                transaction_hash: H256::from([6; 32]),
+                confirmations: 1,
             }];
             subject.more_money_received(transactions);
             subject.account_status(&debtor)
@@ -697,16 +733,22 @@ mod tests {
                 block_number: 1234567890,
                 from: Wallet::new("0xAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA"),
                 gwei_amount: 123456789123456789,
+                transaction_hash: H256::from([7; 32]),
+                confirmations: 1,
             },
             Transaction {
                 block_number: 2345678901,
                 from: Wallet::new("0xBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBB"),
                 gwei_amount: 234567891234567891,
+                transaction_hash: H256::from([8; 32]),
+                confirmations: 1,
             },
             Transaction {
                 block_number: 3456789012,
                 from: Wallet::new("0xCCCCCCCCCCCCCCCCCCCCCCCCCCCCCCCCCCCCCCCC"),
                 gwei_amount: 345678912345678912,
+                transaction_hash: H256::from([9; 32]),
+                confirmations: 1,
             },
         ];
 
@@ -1069,10 +1111,13 @@ mod tests {
             timestamp4,    // below maximum age
         );
 
-        let subject = ReceivableDaoReal::new(conn);
-
-        let top_records = subject.top_records(1_000_000_000, 86400);
-        let total = subject.total();
+        let top_records = top_records(
+            |sql| conn.prepare(sql),
+            SystemTime::now(),
+            1_000_000_000,
+            86400,
+        );
+        let total = total(|sql| conn.prepare(sql));
 
         assert_eq!(
             top_records,
@@ -1099,9 +1144,8 @@ mod tests {
         let conn = DbInitializerReal::new()
             .initialize(&home_dir, DEFAULT_CHAIN_ID, true)
             .unwrap();
-        let subject = ReceivableDaoReal::new(conn);
 
-        let result = subject.total();
+        let result = total(|sql| conn.prepare(sql));
 
         assert_eq!(result, 0)
     }
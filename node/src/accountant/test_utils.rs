@@ -1,9 +1,12 @@
 // Copyright (c) 2017-2019, Substratum LLC (https://substratum.net) and/or its affiliates. All rights reserved.
 
-use crate::accountant::payable_dao::PayableAccount;
+use crate::accountant::payable_dao::{PayableAccount, Payment};
+use crate::accountant::payment_journal::PaymentJournal;
 use crate::accountant::receivable_dao::ReceivableAccount;
 use crate::database::dao_utils::{from_time_t, to_time_t};
 use crate::test_utils::make_wallet;
+use std::cell::RefCell;
+use std::sync::{Arc, Mutex};
 use std::time::SystemTime;
 
 pub fn make_receivable_account(n: u64, expected_delinquent: bool) -> ReceivableAccount {
@@ -28,3 +31,55 @@ pub fn make_payable_account(n: u64) -> PayableAccount {
         pending_payment_transaction: None,
     }
 }
+
+#[derive(Default)]
+pub struct PaymentJournalMock {
+    record_sent_params: Arc<Mutex<Vec<Payment>>>,
+    record_settled_params: Arc<Mutex<Vec<Payment>>>,
+    unsettled_results: RefCell<Vec<Vec<Payment>>>,
+}
+
+impl PaymentJournal for PaymentJournalMock {
+    fn record_sent(&self, payment: &Payment) {
+        self.record_sent_params
+            .lock()
+            .unwrap()
+            .push(payment.clone());
+    }
+
+    fn record_settled(&self, payment: &Payment) {
+        self.record_settled_params
+            .lock()
+            .unwrap()
+            .push(payment.clone());
+    }
+
+    fn unsettled(&self) -> Vec<Payment> {
+        if self.unsettled_results.borrow().is_empty() {
+            vec![]
+        } else {
+            self.unsettled_results.borrow_mut().remove(0)
+        }
+    }
+}
+
+impl PaymentJournalMock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record_sent_params(mut self, params: &Arc<Mutex<Vec<Payment>>>) -> Self {
+        self.record_sent_params = params.clone();
+        self
+    }
+
+    pub fn record_settled_params(mut self, params: &Arc<Mutex<Vec<Payment>>>) -> Self {
+        self.record_settled_params = params.clone();
+        self
+    }
+
+    pub fn unsettled_result(self, result: Vec<Payment>) -> Self {
+        self.unsettled_results.borrow_mut().push(result);
+        self
+    }
+}
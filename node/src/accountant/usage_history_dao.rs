@@ -0,0 +1,175 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use crate::database::connection_wrapper::ConnectionWrapper;
+use crate::database::dao_utils::DaoFactoryReal;
+use rusqlite::types::ToSql;
+use rusqlite::Row;
+
+// A year and a day, so that a trend can be compared with the same day last year
+pub const USAGE_HISTORY_DAYS: u64 = 366;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DailyUsage {
+    pub day: String,
+    pub uptime_seconds: u64,
+    pub routed_bytes: u64,
+    pub earned_gwei: u64,
+    pub neighbors_gained: u64,
+    pub neighbors_lost: u64,
+}
+
+pub trait UsageHistoryDao: Send {
+    // Adds to whatever has already been recorded for the day, and forgets the oldest days
+    fn add_usage(&self, usage: &DailyUsage);
+
+    // Newest first
+    fn history(&self, days: u64) -> Vec<DailyUsage>;
+}
+
+pub trait UsageHistoryDaoFactory {
+    fn make(&self) -> Box<dyn UsageHistoryDao>;
+}
+
+impl UsageHistoryDaoFactory for DaoFactoryReal {
+    fn make(&self) -> Box<dyn UsageHistoryDao> {
+        Box::new(UsageHistoryDaoReal::new(self.make_connection()))
+    }
+}
+
+pub struct UsageHistoryDaoReal {
+    conn: Box<dyn ConnectionWrapper>,
+}
+
+impl UsageHistoryDao for UsageHistoryDaoReal {
+    fn add_usage(&self, usage: &DailyUsage) {
+        let params: &[&dyn ToSql] = &[
+            &(usage.uptime_seconds as i64),
+            &(usage.routed_bytes as i64),
+            &(usage.earned_gwei as i64),
+            &(usage.neighbors_gained as i64),
+            &(usage.neighbors_lost as i64),
+            &usage.day,
+        ];
+        let updated = self
+            .conn
+            .prepare(
+                "update usage_history set uptime_seconds = uptime_seconds + ?, \
+                 routed_bytes = routed_bytes + ?, earned_gwei = earned_gwei + ?, \
+                 neighbors_gained = neighbors_gained + ?, neighbors_lost = neighbors_lost + ? \
+                 where day = ?",
+            )
+            .expect("Internal error")
+            .execute(params)
+            .unwrap_or_else(|e| panic!("Database is corrupt: {:?}", e));
+        if updated == 0 {
+            self.conn
+                .prepare(
+                    "insert into usage_history (uptime_seconds, routed_bytes, earned_gwei, \
+                     neighbors_gained, neighbors_lost, day) values (?, ?, ?, ?, ?, ?)",
+                )
+                .expect("Internal error")
+                .execute(params)
+                .unwrap_or_else(|e| panic!("Database is corrupt: {:?}", e));
+        }
+        let keep: &[&dyn ToSql] = &[&(USAGE_HISTORY_DAYS as i64)];
+        self.conn
+            .prepare(
+                "delete from usage_history where day not in \
+                 (select day from usage_history order by day desc limit ?)",
+            )
+            .expect("Internal error")
+            .execute(keep)
+            .unwrap_or_else(|e| panic!("Database is corrupt: {:?}", e));
+    }
+
+    fn history(&self, days: u64) -> Vec<DailyUsage> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "select day, uptime_seconds, routed_bytes, earned_gwei, neighbors_gained, \
+                 neighbors_lost from usage_history order by day desc limit ?",
+            )
+            .expect("Internal error");
+        let params: &[&dyn ToSql] = &[&(days.min(USAGE_HISTORY_DAYS) as i64)];
+        stmt.query_map(params, Self::row_to_usage)
+            .expect("Database is corrupt")
+            .map(|row| row.unwrap_or_else(|e| panic!("Database is corrupt: {:?}", e)))
+            .collect()
+    }
+}
+
+impl UsageHistoryDaoReal {
+    pub fn new(conn: Box<dyn ConnectionWrapper>) -> UsageHistoryDaoReal {
+        UsageHistoryDaoReal { conn }
+    }
+
+    fn row_to_usage(row: &Row) -> rusqlite::Result<DailyUsage> {
+        Ok(DailyUsage {
+            day: row.get(0)?,
+            uptime_seconds: row.get::<usize, i64>(1)? as u64,
+            routed_bytes: row.get::<usize, i64>(2)? as u64,
+            earned_gwei: row.get::<usize, i64>(3)? as u64,
+            neighbors_gained: row.get::<usize, i64>(4)? as u64,
+            neighbors_lost: row.get::<usize, i64>(5)? as u64,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::db_initializer::{DbInitializer, DbInitializerReal};
+    use masq_lib::test_utils::utils::{ensure_node_home_directory_exists, DEFAULT_CHAIN_ID};
+
+    fn make_usage(day: &str, n: u64) -> DailyUsage {
+        DailyUsage {
+            day: day.to_string(),
+            uptime_seconds: n,
+            routed_bytes: n * 10,
+            earned_gwei: n * 100,
+            neighbors_gained: n * 1000,
+            neighbors_lost: n * 10000,
+        }
+    }
+
+    #[test]
+    fn usage_accumulates_by_day_newest_first() {
+        let home_dir = ensure_node_home_directory_exists(
+            "usage_history_dao",
+            "usage_accumulates_by_day_newest_first",
+        );
+        let subject = UsageHistoryDaoReal::new(
+            DbInitializerReal::new()
+                .initialize(&home_dir, DEFAULT_CHAIN_ID, true)
+                .unwrap(),
+        );
+
+        subject.add_usage(&make_usage("2020-11-01", 1));
+        subject.add_usage(&make_usage("2020-11-02", 2));
+        subject.add_usage(&make_usage("2020-11-02", 3));
+
+        assert_eq!(
+            subject.history(10),
+            vec![make_usage("2020-11-02", 5), make_usage("2020-11-01", 1)]
+        );
+        assert_eq!(subject.history(1), vec![make_usage("2020-11-02", 5)]);
+    }
+
+    #[test]
+    fn oldest_days_are_forgotten() {
+        let home_dir =
+            ensure_node_home_directory_exists("usage_history_dao", "oldest_days_are_forgotten");
+        let subject = UsageHistoryDaoReal::new(
+            DbInitializerReal::new()
+                .initialize(&home_dir, DEFAULT_CHAIN_ID, true)
+                .unwrap(),
+        );
+
+        (0..(USAGE_HISTORY_DAYS + 2)).for_each(|n| {
+            subject.add_usage(&make_usage(&format!("day{:04}", n), 1));
+        });
+
+        let result = subject.history(u64::MAX);
+        assert_eq!(result.len() as u64, USAGE_HISTORY_DAYS);
+        assert_eq!(result.last().unwrap().day, format!("day{:04}", 2));
+    }
+}
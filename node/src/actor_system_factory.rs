@@ -7,22 +7,26 @@ use super::dispatcher::Dispatcher;
 use super::hopper::Hopper;
 use super::neighborhood::Neighborhood;
 use super::proxy_client::ProxyClient;
+use super::proxy_server::stream_resumption::ResumptionStoreReal;
 use super::proxy_server::ProxyServer;
 use super::stream_handler_pool::StreamHandlerPool;
 use super::stream_handler_pool::StreamHandlerPoolSubs;
 use super::stream_messages::PoolBindMessage;
 use super::ui_gateway::UiGateway;
 use crate::banned_dao::{BannedCacheLoader, BannedCacheLoaderReal};
+use crate::blockchain::balance_history::BalanceHistoryDaoReal;
 use crate::blockchain::blockchain_bridge::BlockchainBridge;
 use crate::blockchain::blockchain_interface::{
     BlockchainInterface, BlockchainInterfaceClandestine, BlockchainInterfaceNonClandestine,
 };
+use crate::blockchain::payment_adapter::make_payment_adapter;
 use crate::database::dao_utils::DaoFactoryReal;
 use crate::database::db_initializer::{
     connection_or_panic, DbInitializer, DbInitializerReal, DATABASE_FILE,
 };
 use crate::db_config::config_dao::ConfigDaoReal;
 use crate::db_config::persistent_configuration::PersistentConfigurationReal;
+use crate::metrics_exporter::start_metrics_exporter;
 use crate::sub_lib::accountant::AccountantSubs;
 use crate::sub_lib::blockchain_bridge::BlockchainBridgeSubs;
 use crate::sub_lib::cryptde::CryptDE;
@@ -37,9 +41,13 @@ use crate::sub_lib::proxy_client::ProxyClientSubs;
 use crate::sub_lib::proxy_server::ProxyServerSubs;
 use crate::sub_lib::ui_gateway::UiGatewayConfig;
 use crate::sub_lib::ui_gateway::UiGatewaySubs;
+use crate::trust_dao::{TrustCacheLoader, TrustCacheLoaderReal};
+use crate::updater::start_update_checker;
 use actix::Addr;
 use actix::Recipient;
 use actix::{Actor, Arbiter};
+use masq_lib::messages::{ToMessageBody, UiEffectiveConfigBroadcast};
+use masq_lib::ui_gateway::{MessageTarget, NodeToUiMessage};
 use std::path::PathBuf;
 use std::sync::mpsc;
 use std::sync::mpsc::Sender;
@@ -97,10 +105,16 @@ impl ActorSystemFactoryReal {
             } else {
                 Some(0)
             },
+            &config,
         );
         let proxy_client_subs = actor_factory.make_and_start_proxy_client(ProxyClientConfig {
             cryptde: main_cryptde,
             dns_servers: config.dns_servers.clone(),
+            secure_dns_servers: config.secure_dns_servers.clone(),
+            dns_cache_size: config.dns_cache_size,
+            dns_lookup_threads: config.dns_lookup_threads,
+            exit_policy: config.exit_policy.clone(),
+            stream_idle_timeout_opt: config.stream_idle_timeout_opt,
             exit_service_rate: config
                 .neighborhood_config
                 .mode
@@ -134,11 +148,22 @@ impl ActorSystemFactoryReal {
             &config.data_directory.clone(),
             &db_initializer,
             &BannedCacheLoaderReal {},
+            &TrustCacheLoaderReal {},
         );
         let ui_gateway_subs =
             actor_factory.make_and_start_ui_gateway(config.ui_gateway_config.clone());
         let stream_handler_pool_subs = actor_factory
             .make_and_start_stream_handler_pool(config.clandestine_discriminator_factories.clone());
+        if let Some(port) = config.metrics_port_opt {
+            start_metrics_exporter(port)
+                .unwrap_or_else(|e| panic!("Could not serve metrics on port {}: {}", port, e));
+        }
+        if let Some(update_check_config) = config.update_check_opt.clone() {
+            start_update_checker(
+                update_check_config,
+                ui_gateway_subs.node_to_ui_message_sub.clone(),
+            );
+        }
 
         // collect all the subs
         let peer_actors = PeerActors {
@@ -180,6 +205,19 @@ impl ActorSystemFactoryReal {
         //after we've bound all the actors, send start messages to any actors that need it
         send_start_message!(peer_actors.neighborhood);
 
+        // Nobody is likely to be listening yet; the UiGateway remembers it for later requests
+        peer_actors
+            .ui_gateway
+            .node_to_ui_message_sub
+            .try_send(NodeToUiMessage {
+                target: MessageTarget::AllClients,
+                body: UiEffectiveConfigBroadcast {
+                    config: config.effective_config(),
+                }
+                .tmb(0),
+            })
+            .expect("UiGateway is dead");
+
         //send out the stream handler pool subs (to be bound to listeners)
         tx.send(stream_handler_pool_subs).ok();
     }
@@ -196,6 +234,7 @@ pub trait ActorFactory: Send {
         alias_cryptde: &'static dyn CryptDE,
         is_decentralized: bool,
         consuming_wallet_balance: Option<i64>,
+        config: &BootstrapperConfig,
     ) -> ProxyServerSubs;
     fn make_and_start_hopper(&self, config: HopperConfig) -> HopperSubs;
     fn make_and_start_neighborhood(
@@ -209,6 +248,7 @@ pub trait ActorFactory: Send {
         data_directory: &PathBuf,
         db_initializer: &dyn DbInitializer,
         banned_cache_loader: &dyn BannedCacheLoader,
+        trust_cache_loader: &dyn TrustCacheLoader,
     ) -> AccountantSubs;
     fn make_and_start_ui_gateway(&self, config: UiGatewayConfig) -> UiGatewaySubs;
     fn make_and_start_stream_handler_pool(
@@ -246,14 +286,34 @@ impl ActorFactory for ActorFactoryReal {
         alias_cryptde: &'static dyn CryptDE,
         is_decentralized: bool,
         consuming_wallet_balance: Option<i64>,
+        config: &BootstrapperConfig,
     ) -> ProxyServerSubs {
+        let socks_config_opt = config.socks_config_opt.clone();
+        let multipath_mode = config.multipath_mode;
+        let encrypted_sni_fallback = config.encrypted_sni_fallback.clone();
+        let protocol_sniffing = config.protocol_sniffing;
+        let data_directory = config.data_directory.clone();
+        let chain_id = config.blockchain_bridge_config.chain_id;
         let addr: Addr<ProxyServer> = Arbiter::start(move |_| {
-            ProxyServer::new(
+            let mut proxy_server = ProxyServer::new(
                 main_cryptde,
                 alias_cryptde,
                 is_decentralized,
                 consuming_wallet_balance,
-            )
+                socks_config_opt,
+            );
+            let conn = DbInitializerReal::new()
+                .initialize(&data_directory, chain_id, true)
+                .expect("ProxyServer could not connect to database");
+            let persistent_config = PersistentConfigurationReal::from(conn);
+            proxy_server.load_app_rules(&persistent_config);
+            proxy_server.load_split_tunnel_rules(Box::new(persistent_config));
+            proxy_server.set_multipath_mode(multipath_mode);
+            proxy_server.set_encrypted_sni_fallback(encrypted_sni_fallback);
+            proxy_server.set_protocol_sniffing(protocol_sniffing);
+            proxy_server
+                .load_resumption_tokens(Box::new(ResumptionStoreReal::new(&data_directory)));
+            proxy_server
         });
         ProxyServer::make_subs_from(&addr)
     }
@@ -280,6 +340,7 @@ impl ActorFactory for ActorFactoryReal {
         data_directory: &PathBuf,
         db_initializer: &dyn DbInitializer,
         banned_cache_loader: &dyn BannedCacheLoader,
+        trust_cache_loader: &dyn TrustCacheLoader,
     ) -> AccountantSubs {
         let cloned_config = config.clone();
         let chain_id = config.blockchain_bridge_config.chain_id;
@@ -298,12 +359,33 @@ impl ActorFactory for ActorFactoryReal {
             config.blockchain_bridge_config.chain_id,
             false,
         );
+        let usage_history_dao_factory = DaoFactoryReal::new(
+            data_directory,
+            config.blockchain_bridge_config.chain_id,
+            false,
+        );
+        let trust_dao_factory = DaoFactoryReal::new(
+            data_directory,
+            config.blockchain_bridge_config.chain_id,
+            false,
+        );
+        let financials_dao_factory = DaoFactoryReal::new(
+            data_directory,
+            config.blockchain_bridge_config.chain_id,
+            false,
+        );
         banned_cache_loader.load(connection_or_panic(
             db_initializer,
             data_directory,
             chain_id,
             false,
         ));
+        trust_cache_loader.load(connection_or_panic(
+            db_initializer,
+            data_directory,
+            chain_id,
+            false,
+        ));
         let config_dao_factory = DaoFactoryReal::new(
             data_directory,
             config.blockchain_bridge_config.chain_id,
@@ -315,6 +397,9 @@ impl ActorFactory for ActorFactoryReal {
                 Box::new(payable_dao_factory),
                 Box::new(receivable_dao_factory),
                 Box::new(banned_dao_factory),
+                Box::new(usage_history_dao_factory),
+                Box::new(trust_dao_factory),
+                Box::new(financials_dao_factory),
                 Box::new(config_dao_factory),
             )
         });
@@ -358,6 +443,10 @@ impl ActorFactory for ActorFactoryReal {
                             transport,
                             event_loop_handle,
                             config.blockchain_bridge_config.chain_id,
+                            make_payment_adapter(
+                                config.blockchain_bridge_config.chain_id,
+                                config.blockchain_bridge_config.payment_token_opt.as_ref(),
+                            ),
                         ))
                     }
                     Err(e) => panic!("Invalid blockchain node URL: {:?}", e),
@@ -367,7 +456,7 @@ impl ActorFactory for ActorFactoryReal {
                 )),
             }
         };
-        let config_dao = Box::new(ConfigDaoReal::new(
+        let connect = || {
             db_initializer
                 .initialize(
                     &config.data_directory,
@@ -379,11 +468,17 @@ impl ActorFactory for ActorFactoryReal {
                         "Failed to connect to database at {:?}",
                         &config.data_directory.join(DATABASE_FILE)
                     )
-                }),
-        ));
+                })
+        };
+        let config_dao = Box::new(ConfigDaoReal::new(connect()));
         let persistent_config = Box::new(PersistentConfigurationReal::new(config_dao));
-        let blockchain_bridge =
-            BlockchainBridge::new(config, blockchain_interface, persistent_config);
+        let balance_history_dao = Box::new(BalanceHistoryDaoReal::new(connect()));
+        let blockchain_bridge = BlockchainBridge::new(
+            config,
+            blockchain_interface,
+            persistent_config,
+            balance_history_dao,
+        );
         let addr: Addr<BlockchainBridge> = blockchain_bridge.start();
         BlockchainBridge::make_subs_from(&addr)
     }
@@ -401,32 +496,41 @@ mod tests {
     use crate::stream_messages::AddStreamMsg;
     use crate::stream_messages::RemoveStreamMsg;
     use crate::sub_lib::accountant::AccountantConfig;
+    use crate::sub_lib::accountant::PaymentOverdue_0v1;
     use crate::sub_lib::accountant::ReportRoutingServiceConsumedMessage;
     use crate::sub_lib::accountant::ReportRoutingServiceProvidedMessage;
     use crate::sub_lib::accountant::{
-        ReportExitServiceConsumedMessage, ReportExitServiceProvidedMessage,
+        ReportEarningWalletChangeMessage, ReportExitServiceConsumedMessage,
+        ReportExitServiceProvidedMessage,
     };
     use crate::sub_lib::blockchain_bridge::{BlockchainBridgeConfig, ReportAccountsPayable};
+    use crate::sub_lib::clock_skew::DEFAULT_CLOCK_SKEW_TOLERANCE_SECS;
     use crate::sub_lib::cryptde::PlainData;
     use crate::sub_lib::dispatcher::{InboundClientData, StreamShutdownMsg};
     use crate::sub_lib::hopper::IncipientCoresPackage;
     use crate::sub_lib::hopper::{ExpiredCoresPackage, NoLookupIncipientCoresPackage};
+    use crate::sub_lib::log_rotation::LogRotationConfig;
     use crate::sub_lib::neighborhood::RouteQueryMessage;
     use crate::sub_lib::neighborhood::{
-        DispatcherNodeQueryMessage, GossipFailure_0v1, NodeRecordMetadataMessage,
+        Benchmark_0v1, ConnectionAuditMessage, DispatcherNodeQueryMessage, GossipFailure_0v1,
+        HolePunch_0v1, HopTimingsMessage, NodeRecordMetadataMessage,
     };
     use crate::sub_lib::neighborhood::{NeighborhoodConfig, NodeQueryMessage};
     use crate::sub_lib::neighborhood::{NeighborhoodMode, RemoveNeighborMessage};
     use crate::sub_lib::node_addr::NodeAddr;
     use crate::sub_lib::peer_actors::StartMessage;
     use crate::sub_lib::proxy_client::{
-        ClientResponsePayload_0v1, DnsResolveFailure_0v1, InboundServerData,
+        ClientResponsePayload_0v1, ClientUdpResponsePayload_0v1, DnsResolveFailure_0v1,
+        InboundServerData, InboundServerDatagram, StreamsQuery,
     };
     use crate::sub_lib::proxy_server::{
         AddReturnRouteMessage, AddRouteMessage, ClientRequestPayload_0v1,
+        ClientUdpRequestPayload_0v1, StreamAckPayload_0v1,
     };
+    use crate::sub_lib::proxy_server::{EncryptedSniFallback, MultipathMode, SocksConfig};
     use crate::sub_lib::set_consuming_wallet_message::SetConsumingWalletMessage;
     use crate::sub_lib::stream_handler_pool::DispatcherNodeQueryResponse;
+    use crate::sub_lib::stream_handler_pool::PunchHoleMsg;
     use crate::sub_lib::stream_handler_pool::TransmitDataMsg;
     use crate::sub_lib::ui_gateway::UiGatewayConfig;
     use crate::test_utils::recorder::Recorder;
@@ -436,14 +540,17 @@ mod tests {
     use actix::System;
     use log::LevelFilter;
     use masq_lib::crash_point::CrashPoint;
+    use masq_lib::exit_policy::ExitPolicy;
+    use masq_lib::messages::FromMessageBody;
+    use masq_lib::secure_dns::SecureDnsServer;
     use masq_lib::test_utils::utils::DEFAULT_CHAIN_ID;
     use masq_lib::ui_gateway::NodeFromUiMessage;
-    use masq_lib::ui_gateway::NodeToUiMessage;
     use std::cell::RefCell;
     use std::collections::HashMap;
     use std::net::IpAddr;
     use std::net::Ipv4Addr;
     use std::path::PathBuf;
+    use std::str::FromStr;
     use std::sync::Arc;
     use std::sync::Mutex;
     use std::thread;
@@ -486,6 +593,7 @@ mod tests {
                 from_dispatcher_client: recipient!(addr, TransmitDataMsg),
                 stream_shutdown_sub: recipient!(addr, StreamShutdownMsg),
                 ui_sub: recipient!(addr, NodeFromUiMessage),
+                punch_hole_sub: recipient!(addr, PunchHoleMsg),
             };
             (dispatcher_subs, addr.recipient::<PoolBindMessage>())
         }
@@ -496,6 +604,7 @@ mod tests {
             alias_cryptde: &'a dyn CryptDE,
             is_decentralized: bool,
             consuming_wallet_balance: Option<i64>,
+            config: &BootstrapperConfig,
         ) -> ProxyServerSubs {
             self.parameters
                 .proxy_server_params
@@ -506,6 +615,7 @@ mod tests {
                     alias_cryptde,
                     is_decentralized,
                     consuming_wallet_balance,
+                    config.clone(),
                 ));
             let addr: Addr<Recorder> = ActorFactoryMock::start_recorder(&self.proxy_server);
             ProxyServerSubs {
@@ -517,10 +627,14 @@ mod tests {
                 dns_failure_from_hopper: addr
                     .clone()
                     .recipient::<ExpiredCoresPackage<DnsResolveFailure_0v1>>(),
+                udp_from_hopper: addr
+                    .clone()
+                    .recipient::<ExpiredCoresPackage<ClientUdpResponsePayload_0v1>>(),
                 add_return_route: recipient!(addr, AddReturnRouteMessage),
                 add_route: recipient!(addr, AddRouteMessage),
                 stream_shutdown_sub: recipient!(addr, StreamShutdownMsg),
                 set_consuming_wallet_sub: recipient!(addr, SetConsumingWalletMessage),
+                from_ui_message_sub: recipient!(addr, NodeFromUiMessage),
             }
         }
 
@@ -562,6 +676,14 @@ mod tests {
                 gossip_failure: addr
                     .clone()
                     .recipient::<ExpiredCoresPackage<GossipFailure_0v1>>(),
+                hole_punch: addr
+                    .clone()
+                    .recipient::<ExpiredCoresPackage<HolePunch_0v1>>(),
+                benchmark: addr
+                    .clone()
+                    .recipient::<ExpiredCoresPackage<Benchmark_0v1>>(),
+                hop_timings: recipient!(addr, HopTimingsMessage),
+                connection_audit: recipient!(addr, ConnectionAuditMessage),
                 dispatcher_node_query: recipient!(addr, DispatcherNodeQueryMessage),
                 remove_neighbor: recipient!(addr, RemoveNeighborMessage),
                 stream_shutdown_sub: recipient!(addr, StreamShutdownMsg),
@@ -576,6 +698,7 @@ mod tests {
             data_directory: &PathBuf,
             _db_initializer: &dyn DbInitializer,
             _banned_cache_loader: &dyn BannedCacheLoader,
+            _trust_cache_loader: &dyn TrustCacheLoader,
         ) -> AccountantSubs {
             self.parameters
                 .accountant_params
@@ -600,6 +723,10 @@ mod tests {
                     .recipient::<ReportExitServiceConsumedMessage>(),
                 report_new_payments: recipient!(addr, ReceivedPayments),
                 report_sent_payments: recipient!(addr, SentPayments),
+                report_earning_wallet_change: recipient!(addr, ReportEarningWalletChangeMessage),
+                payment_overdue_from_hopper: addr
+                    .clone()
+                    .recipient::<ExpiredCoresPackage<PaymentOverdue_0v1>>(),
                 ui_message_sub: addr.clone().recipient::<NodeFromUiMessage>(),
             }
         }
@@ -629,6 +756,7 @@ mod tests {
                 remove_sub: recipient!(addr, RemoveStreamMsg),
                 bind: recipient!(addr, PoolBindMessage),
                 node_query_response: recipient!(addr, DispatcherNodeQueryResponse),
+                punch_hole_sub: recipient!(addr, PunchHoleMsg),
             }
         }
 
@@ -646,6 +774,15 @@ mod tests {
                     .recipient::<ExpiredCoresPackage<ClientRequestPayload_0v1>>(),
                 inbound_server_data: recipient!(addr, InboundServerData),
                 dns_resolve_failed: recipient!(addr, DnsResolveFailure_0v1),
+                udp_from_hopper: addr
+                    .clone()
+                    .recipient::<ExpiredCoresPackage<ClientUdpRequestPayload_0v1>>(),
+                stream_ack_from_hopper: addr
+                    .clone()
+                    .recipient::<ExpiredCoresPackage<StreamAckPayload_0v1>>(),
+                inbound_server_datagram: recipient!(addr, InboundServerDatagram),
+                streams_query: recipient!(addr, StreamsQuery),
+                payment_overdue: recipient!(addr, PaymentOverdue_0v1),
             }
         }
 
@@ -681,11 +818,18 @@ mod tests {
         blockchain_bridge: Arc<Mutex<Recording>>,
     }
 
+    type ProxyServerParameters<'a> = (
+        &'a dyn CryptDE,
+        &'a dyn CryptDE,
+        bool,
+        Option<i64>,
+        BootstrapperConfig,
+    );
+
     #[derive(Clone)]
     struct Parameters<'a> {
         proxy_client_params: Arc<Mutex<Option<ProxyClientConfig>>>,
-        proxy_server_params:
-            Arc<Mutex<Option<(&'a dyn CryptDE, &'a dyn CryptDE, bool, Option<i64>)>>>,
+        proxy_server_params: Arc<Mutex<Option<ProxyServerParameters<'a>>>>,
         hopper_params: Arc<Mutex<Option<HopperConfig>>>,
         neighborhood_params: Arc<Mutex<Option<(&'a dyn CryptDE, BootstrapperConfig)>>>,
         accountant_params: Arc<Mutex<Option<(BootstrapperConfig, PathBuf)>>>,
@@ -768,7 +912,10 @@ mod tests {
         let bbconfig = BlockchainBridgeConfig {
             blockchain_service_url: Some("http://λ:8545".to_string()),
             chain_id: DEFAULT_CHAIN_ID,
+            faucet_url_opt: None,
             gas_price: 1,
+            payment_token_opt: None,
+            price_feed_url_opt: None,
         };
         let mut config = BootstrapperConfig::new();
         config.blockchain_bridge_config = bbconfig;
@@ -783,8 +930,35 @@ mod tests {
         let recordings = actor_factory.get_recordings();
         let config = BootstrapperConfig {
             log_level: LevelFilter::Off,
+            log_redaction: true,
+            deterministic_seed_opt: None,
+            cpu_affinity: vec![],
+            data_cap_opt: None,
+            port_reselection: false,
+            sandbox: true,
+            local_discovery: false,
+            bandwidth_benchmark: false,
+            hop_timing: false,
+            gossip_recording: false,
+            dump_neighborhood: false,
+            protocol_sniffing: false,
+            publish_attestations: false,
+            heed_attestations: false,
+            masquerade_profiles: vec![],
+            clock_skew_tolerance_secs: DEFAULT_CLOCK_SKEW_TOLERANCE_SECS,
+            time_servers: vec![],
+            descriptor_signature_opt: None,
             crash_point: CrashPoint::None,
             dns_servers: vec![],
+            secure_dns_servers: vec![],
+            dns_cache_size: 0,
+            dns_lookup_threads: 1,
+            route_search_threads: 1,
+            exit_policy: ExitPolicy::default(),
+            reputation_lists: vec![],
+            stream_idle_timeout_opt: None,
+            multipath_mode: MultipathMode::Off,
+            encrypted_sni_fallback: EncryptedSniFallback::Refuse,
             accountant_config: AccountantConfig {
                 payable_scan_interval: Duration::from_secs(100),
                 payment_received_scan_interval: Duration::from_secs(100),
@@ -793,21 +967,44 @@ mod tests {
             ui_gateway_config: UiGatewayConfig {
                 ui_port: 5335,
                 node_descriptor: String::from("uninitialized"),
+                tls_opt: None,
+                auth_opt: None,
+                rest_port_opt: None,
+                grpc_port_opt: None,
+                health_port_opt: None,
+                data_directory: PathBuf::new(),
             },
             blockchain_bridge_config: BlockchainBridgeConfig {
                 blockchain_service_url: None,
                 chain_id: DEFAULT_CHAIN_ID,
+                faucet_url_opt: None,
                 gas_price: 1,
+                payment_token_opt: None,
+                price_feed_url_opt: None,
             },
             port_configurations: HashMap::new(),
             db_password_opt: None,
             clandestine_port_opt: None,
+            additional_clandestine_ports: vec![],
             earning_wallet: make_wallet("earning"),
+            earning_wallet_change_opt: None,
+            earning_wallet_change_signature_opt: None,
+            neighbor_signers: HashMap::new(),
             consuming_wallet: Some(make_wallet("consuming")),
             data_directory: PathBuf::new(),
             main_cryptde_null_opt: None,
             alias_cryptde_null_opt: None,
             real_user: RealUser::null(),
+            socks_config_opt: None,
+            dns_port: 53,
+            proxied_domains_opt: None,
+            metrics_port_opt: None,
+            update_check_opt: None,
+            geo_db_opt: None,
+            log_rotation: LogRotationConfig::default(),
+            key_rotation_opt: None,
+            post_quantum: false,
+            local_proxy: false,
             neighborhood_config: NeighborhoodConfig {
                 mode: NeighborhoodMode::Standard(
                     NodeAddr::new(&IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4)), &[]),
@@ -823,7 +1020,7 @@ mod tests {
         let subject = ActorSystemFactoryReal {};
 
         let system = System::new("test");
-        subject.make_and_start_actors(config, Box::new(actor_factory));
+        subject.make_and_start_actors(config.clone(), Box::new(actor_factory));
         System::current().stop();
         system.run();
 
@@ -838,6 +1035,15 @@ mod tests {
         Recording::get::<BindMessage>(&recordings.blockchain_bridge, 0);
         Recording::get::<PoolBindMessage>(&recordings.stream_handler_pool, 0);
         Recording::get::<StartMessage>(&recordings.neighborhood, 1);
+        let effective_config_msg = Recording::get::<NodeToUiMessage>(&recordings.ui_gateway, 1);
+        assert_eq!(effective_config_msg.target, MessageTarget::AllClients);
+        assert_eq!(
+            UiEffectiveConfigBroadcast::fmb(effective_config_msg.body)
+                .unwrap()
+                .0
+                .config,
+            config.effective_config()
+        );
     }
 
     #[test]
@@ -847,8 +1053,35 @@ mod tests {
         let parameters = actor_factory.make_parameters();
         let config = BootstrapperConfig {
             log_level: LevelFilter::Off,
+            log_redaction: true,
+            deterministic_seed_opt: None,
+            cpu_affinity: vec![],
+            data_cap_opt: None,
+            port_reselection: false,
+            sandbox: true,
+            local_discovery: false,
+            bandwidth_benchmark: false,
+            hop_timing: false,
+            gossip_recording: false,
+            dump_neighborhood: false,
+            protocol_sniffing: false,
+            publish_attestations: false,
+            heed_attestations: false,
+            masquerade_profiles: vec![],
+            clock_skew_tolerance_secs: DEFAULT_CLOCK_SKEW_TOLERANCE_SECS,
+            time_servers: vec![],
+            descriptor_signature_opt: None,
             crash_point: CrashPoint::None,
             dns_servers: vec![],
+            secure_dns_servers: vec![SecureDnsServer::from_str("tls://9.9.9.9").unwrap()],
+            dns_cache_size: 500,
+            dns_lookup_threads: 6,
+            route_search_threads: 1,
+            exit_policy: ExitPolicy::from_str("category:tor").unwrap(),
+            reputation_lists: vec![],
+            stream_idle_timeout_opt: Some(Duration::from_secs(300)),
+            multipath_mode: MultipathMode::Stripe,
+            encrypted_sni_fallback: EncryptedSniFallback::Prompt,
             accountant_config: AccountantConfig {
                 payable_scan_interval: Duration::from_secs(100),
                 payment_received_scan_interval: Duration::from_secs(100),
@@ -857,21 +1090,47 @@ mod tests {
             ui_gateway_config: UiGatewayConfig {
                 ui_port: 5335,
                 node_descriptor: String::from("NODE-DESCRIPTOR"),
+                tls_opt: None,
+                auth_opt: None,
+                rest_port_opt: None,
+                grpc_port_opt: None,
+                health_port_opt: None,
+                data_directory: PathBuf::new(),
             },
             blockchain_bridge_config: BlockchainBridgeConfig {
                 blockchain_service_url: None,
                 chain_id: DEFAULT_CHAIN_ID,
+                faucet_url_opt: None,
                 gas_price: 1,
+                payment_token_opt: None,
+                price_feed_url_opt: None,
             },
             port_configurations: HashMap::new(),
             db_password_opt: None,
             clandestine_port_opt: None,
+            additional_clandestine_ports: vec![],
             earning_wallet: make_wallet("earning"),
+            earning_wallet_change_opt: None,
+            earning_wallet_change_signature_opt: None,
+            neighbor_signers: HashMap::new(),
             consuming_wallet: Some(make_wallet("consuming")),
             data_directory: PathBuf::new(),
             main_cryptde_null_opt: None,
             alias_cryptde_null_opt: None,
             real_user: RealUser::null(),
+            socks_config_opt: Some(SocksConfig {
+                port: 1080,
+                credentials_opt: None,
+            }),
+            dns_port: 53,
+            proxied_domains_opt: None,
+            metrics_port_opt: None,
+            update_check_opt: None,
+            geo_db_opt: None,
+            log_rotation: LogRotationConfig::default(),
+            key_rotation_opt: None,
+            post_quantum: false,
+            local_proxy: false,
             neighborhood_config: NeighborhoodConfig {
                 mode: NeighborhoodMode::ZeroHop,
             },
@@ -906,11 +1165,23 @@ mod tests {
         assert_eq!(proxy_client_config.exit_service_rate, 0);
         assert_eq!(proxy_client_config.exit_byte_rate, 0);
         assert_eq!(proxy_client_config.dns_servers, config.dns_servers);
+        assert_eq!(
+            proxy_client_config.secure_dns_servers,
+            config.secure_dns_servers
+        );
+        assert_eq!(proxy_client_config.dns_cache_size, 500);
+        assert_eq!(proxy_client_config.dns_lookup_threads, 6);
+        assert_eq!(proxy_client_config.exit_policy, config.exit_policy);
+        assert_eq!(
+            proxy_client_config.stream_idle_timeout_opt,
+            Some(Duration::from_secs(300))
+        );
         let (
             actual_main_cryptde,
             actual_alias_cryptde,
             actual_is_decentralized,
             consuming_wallet_balance,
+            proxy_server_config,
         ) = Parameters::get(parameters.proxy_server_params);
         check_cryptde(actual_main_cryptde);
         check_cryptde(actual_alias_cryptde);
@@ -920,6 +1191,15 @@ mod tests {
         );
         assert_eq!(actual_is_decentralized, false);
         assert_eq!(consuming_wallet_balance, Some(0));
+        assert_eq!(
+            proxy_server_config.socks_config_opt,
+            config.socks_config_opt
+        );
+        assert_eq!(proxy_server_config.multipath_mode, config.multipath_mode);
+        assert_eq!(
+            proxy_server_config.encrypted_sni_fallback,
+            config.encrypted_sni_fallback
+        );
         let (cryptde, neighborhood_config) = Parameters::get(parameters.neighborhood_params);
         check_cryptde(cryptde);
         assert_eq!(
@@ -939,7 +1219,10 @@ mod tests {
             BlockchainBridgeConfig {
                 blockchain_service_url: None,
                 chain_id: DEFAULT_CHAIN_ID,
+                faucet_url_opt: None,
                 gas_price: 1,
+                payment_token_opt: None,
+                price_feed_url_opt: None,
             }
         );
         assert_eq!(
@@ -957,8 +1240,35 @@ mod tests {
         let parameters = actor_factory.make_parameters();
         let config = BootstrapperConfig {
             log_level: LevelFilter::Off,
+            log_redaction: true,
+            deterministic_seed_opt: None,
+            cpu_affinity: vec![],
+            data_cap_opt: None,
+            port_reselection: false,
+            sandbox: true,
+            local_discovery: false,
+            bandwidth_benchmark: false,
+            hop_timing: false,
+            gossip_recording: false,
+            dump_neighborhood: false,
+            protocol_sniffing: false,
+            publish_attestations: false,
+            heed_attestations: false,
+            masquerade_profiles: vec![],
+            clock_skew_tolerance_secs: DEFAULT_CLOCK_SKEW_TOLERANCE_SECS,
+            time_servers: vec![],
+            descriptor_signature_opt: None,
             crash_point: CrashPoint::None,
             dns_servers: vec![],
+            secure_dns_servers: vec![],
+            dns_cache_size: 0,
+            dns_lookup_threads: 1,
+            route_search_threads: 1,
+            exit_policy: ExitPolicy::default(),
+            reputation_lists: vec![],
+            stream_idle_timeout_opt: None,
+            multipath_mode: MultipathMode::Off,
+            encrypted_sni_fallback: EncryptedSniFallback::Refuse,
             accountant_config: AccountantConfig {
                 payable_scan_interval: Duration::from_secs(100),
                 payment_received_scan_interval: Duration::from_secs(100),
@@ -967,21 +1277,44 @@ mod tests {
             ui_gateway_config: UiGatewayConfig {
                 ui_port: 5335,
                 node_descriptor: String::from("NODE-DESCRIPTOR"),
+                tls_opt: None,
+                auth_opt: None,
+                rest_port_opt: None,
+                grpc_port_opt: None,
+                health_port_opt: None,
+                data_directory: PathBuf::new(),
             },
             blockchain_bridge_config: BlockchainBridgeConfig {
                 blockchain_service_url: None,
                 chain_id: DEFAULT_CHAIN_ID,
+                faucet_url_opt: None,
                 gas_price: 1,
+                payment_token_opt: None,
+                price_feed_url_opt: None,
             },
             port_configurations: HashMap::new(),
             db_password_opt: None,
             clandestine_port_opt: None,
+            additional_clandestine_ports: vec![],
             earning_wallet: make_wallet("earning"),
+            earning_wallet_change_opt: None,
+            earning_wallet_change_signature_opt: None,
+            neighbor_signers: HashMap::new(),
             consuming_wallet: None,
             data_directory: PathBuf::new(),
             main_cryptde_null_opt: None,
             alias_cryptde_null_opt: None,
             real_user: RealUser::null(),
+            socks_config_opt: None,
+            dns_port: 53,
+            proxied_domains_opt: None,
+            metrics_port_opt: None,
+            update_check_opt: None,
+            geo_db_opt: None,
+            log_rotation: LogRotationConfig::default(),
+            key_rotation_opt: None,
+            post_quantum: false,
+            local_proxy: false,
             neighborhood_config: NeighborhoodConfig {
                 mode: NeighborhoodMode::Standard(
                     NodeAddr::new(&IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4)), &[]),
@@ -1003,7 +1336,8 @@ mod tests {
 
         System::current().stop();
         system.run();
-        let (_, _, _, consuming_wallet_balance) = Parameters::get(parameters.proxy_server_params);
+        let (_, _, _, consuming_wallet_balance, _) =
+            Parameters::get(parameters.proxy_server_params);
         assert_eq!(consuming_wallet_balance, None);
     }
 
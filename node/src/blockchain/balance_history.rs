@@ -0,0 +1,218 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use crate::bootstrapper::BootstrapperConfig;
+use crate::database::connection_wrapper::ConnectionWrapper;
+use crate::database::db_initializer::{DbInitializer, DbInitializerReal, DATABASE_FILE};
+use crate::node_configurator::node_configurator_standard::{app, standard};
+use crate::node_configurator::RealDirsWrapper;
+use crate::privilege_drop::{PrivilegeDropper, PrivilegeDropperReal};
+use clap::Arg;
+use masq_lib::command::StdStreams;
+use masq_lib::shared_schema::ConfiguratorError;
+use rusqlite::types::ToSql;
+use rusqlite::Row;
+use std::time::Duration;
+
+pub const EXPORT_BALANCE_HISTORY_FLAG: &str = "--export-balance-history";
+// How often the BlockchainBridge checks whether today's balances have been recorded yet
+pub const BALANCE_SNAPSHOT_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+const EXPORT_BALANCE_HISTORY_HELP: &str =
+    "Write the earning wallet balances your Node has recorded, one line a day, oldest first, to standard output \
+     as CSV, and exit without starting the Node.";
+const CSV_HEADER: &str = "date,address,gas_balance_wei,masq_balance_wei,masq_price";
+
+// What the earning wallet held on one UTC day. Balances are in wei, as decimal strings because they
+// can be too big for any integer SQLite has.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BalanceSnapshot {
+    pub day: String,
+    pub address: String,
+    pub gas_balance: String,
+    pub masq_balance: String,
+    pub masq_price_opt: Option<String>,
+}
+
+pub trait BalanceHistoryDao: Send {
+    // A later snapshot for the same day replaces the earlier one. Nothing is ever forgotten: a
+    // year-end report needs the whole year.
+    fn record(&self, snapshot: &BalanceSnapshot);
+
+    // Newest first
+    fn history(&self, days: u64) -> Vec<BalanceSnapshot>;
+}
+
+pub struct BalanceHistoryDaoReal {
+    conn: Box<dyn ConnectionWrapper>,
+}
+
+impl BalanceHistoryDao for BalanceHistoryDaoReal {
+    fn record(&self, snapshot: &BalanceSnapshot) {
+        let params: &[&dyn ToSql] = &[
+            &snapshot.day,
+            &snapshot.address,
+            &snapshot.gas_balance,
+            &snapshot.masq_balance,
+            &snapshot.masq_price_opt,
+        ];
+        self.conn
+            .prepare(
+                "insert or replace into balance_history (day, wallet_address, gas_balance, \
+                 masq_balance, masq_price) values (?, ?, ?, ?, ?)",
+            )
+            .expect("Internal error")
+            .execute(params)
+            .unwrap_or_else(|e| panic!("Database is corrupt: {:?}", e));
+    }
+
+    fn history(&self, days: u64) -> Vec<BalanceSnapshot> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "select day, wallet_address, gas_balance, masq_balance, masq_price \
+                 from balance_history order by day desc limit ?",
+            )
+            .expect("Internal error");
+        let params: &[&dyn ToSql] = &[&(days.min(i64::MAX as u64) as i64)];
+        stmt.query_map(params, Self::row_to_snapshot)
+            .expect("Database is corrupt")
+            .map(|row| row.unwrap_or_else(|e| panic!("Database is corrupt: {:?}", e)))
+            .collect()
+    }
+}
+
+impl BalanceHistoryDaoReal {
+    pub fn new(conn: Box<dyn ConnectionWrapper>) -> BalanceHistoryDaoReal {
+        BalanceHistoryDaoReal { conn }
+    }
+
+    fn row_to_snapshot(row: &Row) -> rusqlite::Result<BalanceSnapshot> {
+        Ok(BalanceSnapshot {
+            day: row.get(0)?,
+            address: row.get(1)?,
+            gas_balance: row.get(2)?,
+            masq_balance: row.get(3)?,
+            masq_price_opt: row.get(4)?,
+        })
+    }
+}
+
+// Takes the snapshots newest first, as the DAO gives them, and writes them oldest first, the way a
+// spreadsheet expects them. A day recorded without a price has an empty price column.
+pub fn balance_history_csv(snapshots: &[BalanceSnapshot]) -> String {
+    let mut csv = format!("{}\n", CSV_HEADER);
+    snapshots.iter().rev().for_each(|snapshot| {
+        csv.push_str(&format!(
+            "{},{},{},{},{}\n",
+            snapshot.day,
+            snapshot.address,
+            snapshot.gas_balance,
+            snapshot.masq_balance,
+            snapshot.masq_price_opt.as_deref().unwrap_or("")
+        ))
+    });
+    csv
+}
+
+pub fn export_balance_history_mode(
+    args: &[String],
+    streams: &mut StdStreams,
+) -> Result<i32, ConfiguratorError> {
+    let app = app().arg(
+        Arg::with_name("export-balance-history")
+            .long("export-balance-history")
+            .required(true)
+            .takes_value(false)
+            .help(EXPORT_BALANCE_HISTORY_HELP),
+    );
+    let dirs_wrapper = RealDirsWrapper {};
+    let multi_config =
+        standard::make_service_mode_multi_config(&dirs_wrapper, &app, args, streams)?;
+    let mut config = BootstrapperConfig::new();
+    standard::privileged_parse_args(&dirs_wrapper, &multi_config, &mut config, streams)?;
+    PrivilegeDropperReal::new().drop_privileges(&config.real_user);
+    let conn = DbInitializerReal::new()
+        .initialize(
+            &config.data_directory,
+            config.blockchain_bridge_config.chain_id,
+            false,
+        )
+        .map_err(|e| {
+            ConfiguratorError::failed(
+                "export-balance-history",
+                &format!(
+                    "Couldn't open the database at {:?}: {:?}",
+                    config.data_directory.join(DATABASE_FILE),
+                    e
+                ),
+            )
+        })?;
+    let snapshots = BalanceHistoryDaoReal::new(conn).history(u64::MAX);
+    write!(streams.stdout, "{}", balance_history_csv(&snapshots)).expect("write! failed");
+    Ok(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use masq_lib::test_utils::utils::{ensure_node_home_directory_exists, DEFAULT_CHAIN_ID};
+
+    fn make_snapshot(day: &str, n: u64, price_opt: Option<&str>) -> BalanceSnapshot {
+        BalanceSnapshot {
+            day: day.to_string(),
+            address: "0xcafedeadbeefbabefacecafedeadbeefbabeface".to_string(),
+            gas_balance: format!("{}000000000000000000000", n),
+            masq_balance: format!("{}", n * 1000),
+            masq_price_opt: price_opt.map(|price| price.to_string()),
+        }
+    }
+
+    #[test]
+    fn snapshots_are_kept_one_a_day_newest_first() {
+        let home_dir = ensure_node_home_directory_exists(
+            "balance_history",
+            "snapshots_are_kept_one_a_day_newest_first",
+        );
+        let subject = BalanceHistoryDaoReal::new(
+            DbInitializerReal::new()
+                .initialize(&home_dir, DEFAULT_CHAIN_ID, true)
+                .unwrap(),
+        );
+
+        subject.record(&make_snapshot("2020-12-30", 1, Some("0.0421")));
+        subject.record(&make_snapshot("2020-12-31", 2, None));
+        subject.record(&make_snapshot("2020-12-31", 3, Some("0.0433")));
+
+        assert_eq!(
+            subject.history(u64::MAX),
+            vec![
+                make_snapshot("2020-12-31", 3, Some("0.0433")),
+                make_snapshot("2020-12-30", 1, Some("0.0421")),
+            ]
+        );
+        assert_eq!(
+            subject.history(1),
+            vec![make_snapshot("2020-12-31", 3, Some("0.0433"))]
+        );
+    }
+
+    #[test]
+    fn csv_lists_the_days_oldest_first_with_empty_prices_where_there_were_none() {
+        let snapshots = vec![
+            make_snapshot("2020-12-31", 2, None),
+            make_snapshot("2020-12-30", 1, Some("0.0421")),
+        ];
+
+        let result = balance_history_csv(&snapshots);
+
+        assert_eq!(
+            result,
+            "date,address,gas_balance_wei,masq_balance_wei,masq_price\n\
+             2020-12-30,0xcafedeadbeefbabefacecafedeadbeefbabeface,1000000000000000000000,1000,0.0421\n\
+             2020-12-31,0xcafedeadbeefbabefacecafedeadbeefbabeface,2000000000000000000000,2000,\n"
+        );
+        assert_eq!(
+            balance_history_csv(&[]),
+            "date,address,gas_balance_wei,masq_balance_wei,masq_price\n"
+        );
+    }
+}
@@ -4,7 +4,16 @@ use bip39::{Language, Mnemonic, MnemonicType, Seed};
 use ethsign::keyfile::Crypto;
 use ethsign::Protected;
 use rustc_hex::{FromHex, ToHex};
-use std::num::NonZeroU32;
+use sodiumoxide::crypto::pwhash::argon2id13;
+use sodiumoxide::crypto::secretbox;
+
+// Values encrypted with a database password carry the Argon2id parameters their key was derived
+// with in front of the ciphertext, like this:
+//     argon2id$m=<memlimit bytes>,t=<opslimit>$<salt>$<nonce and ciphertext>
+// Anything without the prefix was encrypted by an older Node with a cheap PBKDF2 derivation.
+pub const ARGON2ID_PREFIX: &str = "argon2id$";
+pub const ARGON2ID_OPSLIMIT: argon2id13::OpsLimit = argon2id13::OPSLIMIT_INTERACTIVE;
+pub const ARGON2ID_MEMLIMIT: argon2id13::MemLimit = argon2id13::MEMLIMIT_INTERACTIVE;
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Bip39Error {
@@ -30,26 +39,65 @@ impl Bip39 {
     }
 
     pub fn encrypt_bytes(seed: &dyn AsRef<[u8]>, db_password: &str) -> Result<String, Bip39Error> {
-        match Crypto::encrypt(
-            seed.as_ref(),
-            &Protected::new(db_password.as_bytes()),
-            NonZeroU32::new(10240).expect("Internal error"),
-        ) {
-            Ok(crypto) => match serde_cbor::to_vec(&crypto) {
-                Ok(cipher_seed) => Ok(cipher_seed.to_hex()),
-                Err(e) => Err(Bip39Error::SerializationFailure(format!(
-                    "Failed to serialize: {:?}",
-                    e
-                ))),
-            },
-            Err(e) => Err(Bip39Error::EncryptionFailure(format!(
-                "Failed to encrypt: {:?}",
-                e
-            ))),
-        }
+        let salt = argon2id13::gen_salt();
+        let key = Self::derive_key(db_password, &salt, ARGON2ID_OPSLIMIT, ARGON2ID_MEMLIMIT)?;
+        let nonce = secretbox::gen_nonce();
+        let mut sealed = nonce.0.to_vec();
+        sealed.extend(secretbox::seal(seed.as_ref(), &nonce, &key));
+        Ok(format!(
+            "{}m={},t={}${}${}",
+            ARGON2ID_PREFIX,
+            ARGON2ID_MEMLIMIT.0,
+            ARGON2ID_OPSLIMIT.0,
+            salt.0.to_hex::<String>(),
+            sealed.to_hex::<String>()
+        ))
     }
 
     pub fn decrypt_bytes(crypt_string: &str, db_password: &str) -> Result<PlainData, Bip39Error> {
+        if crypt_string.starts_with(ARGON2ID_PREFIX) {
+            Self::decrypt_argon2id_bytes(crypt_string, db_password)
+        } else {
+            Self::decrypt_pbkdf2_bytes(crypt_string, db_password)
+        }
+    }
+
+    // True if the value was encrypted by an older Node, or with weaker Argon2id parameters than
+    // this one uses; decrypting it and encrypting it again will bring it up to date
+    pub fn needs_reencryption(crypt_string: &str) -> bool {
+        match Self::parse_argon2id(crypt_string) {
+            Ok((opslimit, memlimit, _, _)) => {
+                opslimit.0 < ARGON2ID_OPSLIMIT.0 || memlimit.0 < ARGON2ID_MEMLIMIT.0
+            }
+            Err(_) => true,
+        }
+    }
+
+    fn decrypt_argon2id_bytes(
+        crypt_string: &str,
+        db_password: &str,
+    ) -> Result<PlainData, Bip39Error> {
+        let (opslimit, memlimit, salt, sealed) = Self::parse_argon2id(crypt_string)?;
+        if sealed.len() < secretbox::NONCEBYTES {
+            return Err(Bip39Error::DeserializationFailure(
+                "Ciphertext is too short".to_string(),
+            ));
+        }
+        let nonce =
+            secretbox::Nonce::from_slice(&sealed[..secretbox::NONCEBYTES]).expect("Internal error");
+        let key = Self::derive_key(db_password, &salt, opslimit, memlimit)?;
+        match secretbox::open(&sealed[secretbox::NONCEBYTES..], &nonce, &key) {
+            Ok(plain_data) => Ok(PlainData::new(&plain_data)),
+            Err(_) => Err(Bip39Error::DecryptionFailure(
+                "Wrong password or corrupt data".to_string(),
+            )),
+        }
+    }
+
+    fn decrypt_pbkdf2_bytes(
+        crypt_string: &str,
+        db_password: &str,
+    ) -> Result<PlainData, Bip39Error> {
         match crypt_string.from_hex::<Vec<u8>>() {
             Ok(cipher_seed_slice) => match serde_cbor::from_slice::<Crypto>(&cipher_seed_slice) {
                 Ok(crypto) => match crypto.decrypt(&Protected::new(db_password)) {
@@ -62,6 +110,72 @@ impl Bip39 {
         }
     }
 
+    #[allow(clippy::type_complexity)]
+    fn parse_argon2id(
+        crypt_string: &str,
+    ) -> Result<
+        (
+            argon2id13::OpsLimit,
+            argon2id13::MemLimit,
+            argon2id13::Salt,
+            Vec<u8>,
+        ),
+        Bip39Error,
+    > {
+        let malformed = || {
+            Bip39Error::DeserializationFailure(format!(
+                "Malformed Argon2id value: {}",
+                crypt_string
+            ))
+        };
+        if !crypt_string.starts_with(ARGON2ID_PREFIX) {
+            return Err(malformed());
+        }
+        let pieces: Vec<&str> = crypt_string[ARGON2ID_PREFIX.len()..].split('$').collect();
+        if pieces.len() != 3 {
+            return Err(malformed());
+        }
+        let (mut memlimit_opt, mut opslimit_opt) = (None, None);
+        for parameter in pieces[0].split(',') {
+            match parameter.split_at(parameter.find('=').ok_or_else(malformed)?) {
+                ("m", value) => memlimit_opt = value[1..].parse::<usize>().ok(),
+                ("t", value) => opslimit_opt = value[1..].parse::<usize>().ok(),
+                _ => return Err(malformed()),
+            }
+        }
+        let salt_bytes = pieces[1]
+            .from_hex::<Vec<u8>>()
+            .map_err(|e| Bip39Error::ConversionError(format!("{:?}", e)))?;
+        let salt = argon2id13::Salt::from_slice(&salt_bytes).ok_or_else(malformed)?;
+        let sealed = pieces[2]
+            .from_hex::<Vec<u8>>()
+            .map_err(|e| Bip39Error::ConversionError(format!("{:?}", e)))?;
+        match (opslimit_opt, memlimit_opt) {
+            (Some(opslimit), Some(memlimit)) => Ok((
+                argon2id13::OpsLimit(opslimit),
+                argon2id13::MemLimit(memlimit),
+                salt,
+                sealed,
+            )),
+            _ => Err(malformed()),
+        }
+    }
+
+    fn derive_key(
+        db_password: &str,
+        salt: &argon2id13::Salt,
+        opslimit: argon2id13::OpsLimit,
+        memlimit: argon2id13::MemLimit,
+    ) -> Result<secretbox::Key, Bip39Error> {
+        let mut key = secretbox::Key([0u8; secretbox::KEYBYTES]);
+        match argon2id13::derive_key(&mut key.0, db_password.as_bytes(), salt, opslimit, memlimit) {
+            Ok(_) => Ok(key),
+            Err(_) => Err(Bip39Error::EncryptionFailure(
+                "Argon2id key derivation failed".to_string(),
+            )),
+        }
+    }
+
     pub fn language_from_name(name: &str) -> Language {
         match name.to_lowercase().as_str() {
             "english" => Language::English,
@@ -109,6 +223,94 @@ impl Bip39 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::num::NonZeroU32;
+
+    fn encrypt_pbkdf2_bytes(seed: &dyn AsRef<[u8]>, db_password: &str) -> String {
+        let crypto = Crypto::encrypt(
+            seed.as_ref(),
+            &Protected::new(db_password.as_bytes()),
+            NonZeroU32::new(10240).unwrap(),
+        )
+        .unwrap();
+        serde_cbor::to_vec(&crypto).unwrap().to_hex()
+    }
+
+    #[test]
+    fn encrypted_bytes_carry_their_argon2id_parameters() {
+        let result = Bip39::encrypt_bytes(&b"booga".to_vec(), "password").unwrap();
+
+        let expected_prefix = format!(
+            "argon2id$m={},t={}$",
+            ARGON2ID_MEMLIMIT.0, ARGON2ID_OPSLIMIT.0
+        );
+        assert!(result.starts_with(&expected_prefix), "{}", result);
+        assert_eq!(
+            Bip39::decrypt_bytes(&result, "password"),
+            Ok(PlainData::new(b"booga"))
+        );
+        assert_eq!(Bip39::needs_reencryption(&result), false);
+    }
+
+    #[test]
+    fn argon2id_bytes_do_not_decrypt_with_the_wrong_password() {
+        let encrypted = Bip39::encrypt_bytes(&b"booga".to_vec(), "password").unwrap();
+
+        let result = Bip39::decrypt_bytes(&encrypted, "drowssap");
+
+        assert_eq!(
+            result,
+            Err(Bip39Error::DecryptionFailure(
+                "Wrong password or corrupt data".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn values_encrypted_by_older_nodes_still_decrypt_but_need_reencryption() {
+        let encrypted = encrypt_pbkdf2_bytes(&b"booga".to_vec(), "password");
+
+        let result = Bip39::decrypt_bytes(&encrypted, "password");
+
+        assert_eq!(result, Ok(PlainData::new(b"booga")));
+        assert_eq!(Bip39::needs_reencryption(&encrypted), true);
+    }
+
+    #[test]
+    fn values_encrypted_with_weaker_parameters_still_decrypt_but_need_reencryption() {
+        let salt = argon2id13::gen_salt();
+        let key = Bip39::derive_key(
+            "password",
+            &salt,
+            argon2id13::OpsLimit(1),
+            argon2id13::MemLimit(8192),
+        )
+        .unwrap();
+        let nonce = secretbox::gen_nonce();
+        let mut sealed = nonce.0.to_vec();
+        sealed.extend(secretbox::seal(b"booga", &nonce, &key));
+        let encrypted = format!(
+            "argon2id$m=8192,t=1${}${}",
+            salt.0.to_hex::<String>(),
+            sealed.to_hex::<String>()
+        );
+
+        let result = Bip39::decrypt_bytes(&encrypted, "password");
+
+        assert_eq!(result, Ok(PlainData::new(b"booga")));
+        assert_eq!(Bip39::needs_reencryption(&encrypted), true);
+    }
+
+    #[test]
+    fn malformed_argon2id_values_are_reported() {
+        let result = Bip39::decrypt_bytes("argon2id$m=8192$0000", "password");
+
+        assert_eq!(
+            result,
+            Err(Bip39Error::DeserializationFailure(
+                "Malformed Argon2id value: argon2id$m=8192$0000".to_string()
+            ))
+        );
+    }
 
     #[test]
     fn returns_conversion_error_for_odd_number_of_hex_digits_appropriately() {
@@ -1,37 +1,73 @@
 // Copyright (c) 2017-2019, Substratum LLC (https://substratum.net) and/or its affiliates. All rights reserved.
 
 use crate::accountant::payable_dao::Payment;
+use crate::accountant::payment_journal::{PaymentJournal, PaymentJournalReal};
+use crate::blockchain::balance_history::{
+    BalanceHistoryDao, BalanceSnapshot, BALANCE_SNAPSHOT_INTERVAL,
+};
 use crate::blockchain::blockchain_interface::{
-    BlockchainError, BlockchainInterface, BlockchainResult, Transaction,
+    chain_name_from_id, BlockchainError, BlockchainInterface, BlockchainResult, Transaction,
 };
+use crate::blockchain::faucet::{request_test_funds, FaucetClient, FaucetClientReal};
+use crate::blockchain::price_feed::{fetch_price, PriceFeed, PriceFeedReal};
 use crate::bootstrapper::BootstrapperConfig;
 use crate::db_config::persistent_configuration::PersistentConfiguration;
 use crate::sub_lib::blockchain_bridge::BlockchainBridgeSubs;
 use crate::sub_lib::blockchain_bridge::ReportAccountsPayable;
+use crate::sub_lib::clock::{Clock, ClockReal};
 use crate::sub_lib::logger::Logger;
 use crate::sub_lib::peer_actors::BindMessage;
+use crate::sub_lib::redacted::Redacted;
 use crate::sub_lib::set_consuming_wallet_message::SetConsumingWalletMessage;
 use crate::sub_lib::utils::handle_ui_crash_request;
 use crate::sub_lib::wallet::Wallet;
+use actix::AsyncContext;
 use actix::Context;
 use actix::Handler;
 use actix::Message;
 use actix::{Actor, MessageResult};
 use actix::{Addr, Recipient};
+use chrono::{DateTime, Utc};
 use masq_lib::crash_point::CrashPoint;
-use masq_lib::messages::{FromMessageBody, UiCrashRequest};
-use masq_lib::ui_gateway::NodeFromUiMessage;
+use masq_lib::messages::{
+    FromMessageBody, ToMessageBody, UiBalanceDay, UiBalanceHistoryRequest,
+    UiBalanceHistoryResponse, UiCrashRequest, UiRequestTestFundsRequest,
+    UiRequestTestFundsResponse, UiWalletBalances, UiWalletBalancesRequest,
+    UiWalletBalancesResponse, BLOCKCHAIN_QUERY_ERROR, TEST_FUNDS_ERROR,
+};
+use masq_lib::ui_gateway::MessagePath::Conversation;
+use masq_lib::ui_gateway::MessageTarget::ClientId;
+use masq_lib::ui_gateway::{MessageBody, NodeFromUiMessage, NodeToUiMessage};
 use std::convert::TryFrom;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 
 pub const CRASH_KEY: &str = "BLOCKCHAINBRIDGE";
+// However many UIs ask, the blockchain service is asked for balances at most this often
+pub const BALANCE_CACHE_LIFETIME: Duration = Duration::from_secs(60);
+// A blockchain service that has just failed gets a rest before it's asked again
+pub const FAILED_BALANCE_QUERY_BACKOFF: Duration = Duration::from_secs(10);
 
 pub struct BlockchainBridge {
     consuming_wallet: Option<Wallet>,
+    earning_wallet: Wallet,
     blockchain_interface: Box<dyn BlockchainInterface>,
     logger: Logger,
     persistent_config: Box<dyn PersistentConfiguration>,
+    payment_journal: Box<dyn PaymentJournal>,
     set_consuming_wallet_subs: Option<Vec<Recipient<SetConsumingWalletMessage>>>,
+    ui_message_sub: Option<Recipient<NodeToUiMessage>>,
+    balance_cache_opt: Option<(SystemTime, Result<UiWalletBalancesResponse, String>)>,
     crashable: bool,
+    clock: Arc<dyn Clock>,
+    chain_verified: bool,
+    chain_id: u8,
+    faucet_url_opt: Option<String>,
+    faucet_client: Box<dyn FaucetClient>,
+    balance_history_dao: Box<dyn BalanceHistoryDao>,
+    price_feed_url_opt: Option<String>,
+    price_feed: Box<dyn PriceFeed>,
+    local_proxy: bool,
 }
 
 impl Actor for BlockchainBridge {
@@ -41,7 +77,7 @@ impl Actor for BlockchainBridge {
 impl Handler<BindMessage> for BlockchainBridge {
     type Result = ();
 
-    fn handle(&mut self, msg: BindMessage, _ctx: &mut Self::Context) -> Self::Result {
+    fn handle(&mut self, msg: BindMessage, ctx: &mut Self::Context) -> Self::Result {
         self.set_consuming_wallet_subs = Some(vec![
             msg.peer_actors
                 .neighborhood
@@ -49,16 +85,32 @@ impl Handler<BindMessage> for BlockchainBridge {
                 .clone(),
             msg.peer_actors.proxy_server.set_consuming_wallet_sub,
         ]);
+        self.ui_message_sub = Some(msg.peer_actors.ui_gateway.node_to_ui_message_sub);
         match self.consuming_wallet.as_ref() {
             Some(wallet) => debug!(
                 self.logger,
-                "Received BindMessage; consuming wallet address {}", wallet
+                "Received BindMessage; consuming wallet address {}",
+                Redacted(wallet)
             ),
             None => debug!(
                 self.logger,
                 "Received BindMessage; no consuming wallet address specified"
             ),
         }
+        match self.verify_chain() {
+            Err(BlockchainError::WrongChain { .. }) | Ok(()) => (),
+            Err(e) => warning!(
+                self.logger,
+                "Couldn't ask the blockchain service which chain it's for; will ask again before using it: {}",
+                e
+            ),
+        }
+        if !self.local_proxy {
+            ctx.run_interval(
+                self.clock.real_interval(BALANCE_SNAPSHOT_INTERVAL),
+                |bridge, _ctx| bridge.record_balance_snapshot(),
+            );
+        }
     }
 }
 
@@ -80,10 +132,14 @@ impl Handler<RetrieveTransactions> for BlockchainBridge {
         msg: RetrieveTransactions,
         _ctx: &mut Self::Context,
     ) -> <Self as Handler<RetrieveTransactions>>::Result {
-        MessageResult(
+        let result = self.verify_chain().and_then(|_| {
             self.blockchain_interface
-                .retrieve_transactions(msg.start_block, &msg.recipient),
-        )
+                .retrieve_transactions(msg.start_block, &msg.recipient)
+        });
+        if result == Err(BlockchainError::QueryFailed) {
+            self.chain_verified = false;
+        }
+        MessageResult(result)
     }
 }
 
@@ -95,6 +151,9 @@ impl Handler<ReportAccountsPayable> for BlockchainBridge {
         msg: ReportAccountsPayable,
         _ctx: &mut Self::Context,
     ) -> <Self as Handler<ReportAccountsPayable>>::Result {
+        if let Err(e) = self.verify_chain() {
+            return MessageResult(Err(e.to_string()));
+        }
         MessageResult(match self.consuming_wallet.as_ref() {
             Some(consuming_wallet) => Ok(msg
                 .accounts
@@ -114,13 +173,23 @@ impl Handler<ReportAccountsPayable> for BlockchainBridge {
                                 nonce,
                                 self.persistent_config.gas_price().unwrap().unwrap(),
                             ) {
-                                Ok(hash) => Ok(Payment::new(
-                                    payable.wallet.clone(),
-                                    u64::try_from(payable.balance).unwrap_or_else(|_| {
-                                        panic!("Lost payable amount precision: {}", payable.balance)
-                                    }),
-                                    hash,
-                                )),
+                                Ok(hash) => {
+                                    let payment = Payment {
+                                        timestamp: self.clock.now(),
+                                        ..Payment::new(
+                                            payable.wallet.clone(),
+                                            u64::try_from(payable.balance).unwrap_or_else(|_| {
+                                                panic!(
+                                                    "Lost payable amount precision: {}",
+                                                    payable.balance
+                                                )
+                                            }),
+                                            hash,
+                                        )
+                                    };
+                                    self.payment_journal.record_sent(&payment);
+                                    Ok(payment)
+                                }
                                 Err(e) => Err(e),
                             }
                         }
@@ -137,7 +206,13 @@ impl Handler<NodeFromUiMessage> for BlockchainBridge {
     type Result = ();
 
     fn handle(&mut self, msg: NodeFromUiMessage, _ctx: &mut Self::Context) -> Self::Result {
-        if let Ok((crash_request, _)) = UiCrashRequest::fmb(msg.body) {
+        if let Ok((_, context_id)) = UiWalletBalancesRequest::fmb(msg.body.clone()) {
+            self.handle_wallet_balances(msg.client_id, context_id)
+        } else if let Ok((_, context_id)) = UiRequestTestFundsRequest::fmb(msg.body.clone()) {
+            self.handle_request_test_funds(msg.client_id, context_id)
+        } else if let Ok((request, context_id)) = UiBalanceHistoryRequest::fmb(msg.body.clone()) {
+            self.handle_balance_history(msg.client_id, context_id, request)
+        } else if let Ok((crash_request, _)) = UiCrashRequest::fmb(msg.body) {
             handle_ui_crash_request(crash_request, &self.logger, self.crashable, CRASH_KEY)
         }
     }
@@ -148,17 +223,37 @@ impl BlockchainBridge {
         config: &BootstrapperConfig,
         blockchain_interface: Box<dyn BlockchainInterface>,
         persistent_config: Box<dyn PersistentConfiguration>,
+        balance_history_dao: Box<dyn BalanceHistoryDao>,
     ) -> BlockchainBridge {
         BlockchainBridge {
             consuming_wallet: config.consuming_wallet.clone(),
+            earning_wallet: config.earning_wallet.clone(),
             blockchain_interface,
             logger: Logger::new("BlockchainBridge"),
             persistent_config,
+            payment_journal: Box::new(PaymentJournalReal::new(&config.data_directory)),
             set_consuming_wallet_subs: None,
+            ui_message_sub: None,
+            balance_cache_opt: None,
             crashable: config.crash_point == CrashPoint::Message,
+            clock: Arc::new(ClockReal {}),
+            chain_verified: false,
+            chain_id: config.blockchain_bridge_config.chain_id,
+            faucet_url_opt: config.blockchain_bridge_config.faucet_url_opt.clone(),
+            faucet_client: Box::new(FaucetClientReal {}),
+            balance_history_dao,
+            price_feed_url_opt: config.blockchain_bridge_config.price_feed_url_opt.clone(),
+            price_feed: Box::new(PriceFeedReal {}),
+            local_proxy: config.local_proxy,
         }
     }
 
+    // For harnesses that play out weeks of payments on a VirtualClock
+    #[cfg(any(test, feature = "virtual_time"))]
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
     pub fn make_subs_from(addr: &Addr<BlockchainBridge>) -> BlockchainBridgeSubs {
         BlockchainBridgeSubs {
             bind: recipient!(addr, BindMessage),
@@ -167,21 +262,253 @@ impl BlockchainBridge {
             ui_sub: recipient!(addr, NodeFromUiMessage),
         }
     }
+
+    // Failures are remembered too, for a shorter while, so that a UI asking over and over can't
+    // hammer a blockchain service that's already in trouble
+    fn handle_wallet_balances(&mut self, client_id: u64, context_id: u64) {
+        let now = self.clock.now();
+        let age = |read_at: &SystemTime| {
+            now.duration_since(*read_at)
+                .unwrap_or_else(|_| Duration::new(0, 0))
+        };
+        let fresh = match self.balance_cache_opt.as_ref() {
+            Some((read_at, Ok(_))) => age(read_at) < BALANCE_CACHE_LIFETIME,
+            Some((read_at, Err(_))) => age(read_at) < FAILED_BALANCE_QUERY_BACKOFF,
+            None => false,
+        };
+        if !fresh {
+            let result = self.query_wallet_balances();
+            if let Err(e) = &result {
+                warning!(self.logger, "{}", e);
+            }
+            self.balance_cache_opt = Some((now, result));
+        }
+        let (read_at, result) = self
+            .balance_cache_opt
+            .as_ref()
+            .expect("Balances disappeared");
+        let body = match result {
+            Ok(response) => UiWalletBalancesResponse {
+                age: age(read_at).as_secs(),
+                ..response.clone()
+            }
+            .tmb(context_id),
+            Err(e) => MessageBody {
+                opcode: UiWalletBalancesRequest::type_opcode().to_string(),
+                path: Conversation(context_id),
+                payload: Err((BLOCKCHAIN_QUERY_ERROR, e.clone())),
+            },
+        };
+        self.ui_message_sub
+            .as_ref()
+            .expect("UiGateway not bound")
+            .try_send(NodeToUiMessage {
+                target: ClientId(client_id),
+                body,
+            })
+            .expect("UiGateway is dead");
+    }
+
+    fn handle_request_test_funds(&mut self, client_id: u64, context_id: u64) {
+        let body = match request_test_funds(
+            self.faucet_client.as_ref(),
+            self.faucet_url_opt.as_deref(),
+            self.chain_id,
+            self.consuming_wallet.as_ref(),
+        ) {
+            Ok(transaction) => {
+                let address = self
+                    .consuming_wallet
+                    .as_ref()
+                    .expect("Consuming wallet disappeared")
+                    .to_string();
+                info!(
+                    self.logger,
+                    "Test funds are on their way to {} in transaction {}", address, transaction
+                );
+                UiRequestTestFundsResponse {
+                    address,
+                    transaction,
+                }
+                .tmb(context_id)
+            }
+            Err(e) => {
+                warning!(self.logger, "{}", e);
+                MessageBody {
+                    opcode: UiRequestTestFundsRequest::type_opcode().to_string(),
+                    path: Conversation(context_id),
+                    payload: Err((TEST_FUNDS_ERROR, e)),
+                }
+            }
+        };
+        self.ui_message_sub
+            .as_ref()
+            .expect("UiGateway not bound")
+            .try_send(NodeToUiMessage {
+                target: ClientId(client_id),
+                body,
+            })
+            .expect("UiGateway is dead");
+    }
+
+    fn handle_balance_history(
+        &mut self,
+        client_id: u64,
+        context_id: u64,
+        request: UiBalanceHistoryRequest,
+    ) {
+        let days = self
+            .balance_history_dao
+            .history(request.days)
+            .into_iter()
+            .map(|snapshot| UiBalanceDay {
+                date: snapshot.day,
+                address: snapshot.address,
+                gas_balance: snapshot.gas_balance,
+                masq_balance: snapshot.masq_balance,
+                masq_price_opt: snapshot.masq_price_opt,
+            })
+            .collect();
+        self.ui_message_sub
+            .as_ref()
+            .expect("UiGateway not bound")
+            .try_send(NodeToUiMessage {
+                target: ClientId(client_id),
+                body: UiBalanceHistoryResponse { days }.tmb(context_id),
+            })
+            .expect("UiGateway is dead");
+    }
+
+    // Once a UTC day, at the first interval the Node is running for. Balances that can't be read
+    // are tried again at the next interval; a price that can't be read is just left out.
+    fn record_balance_snapshot(&mut self) {
+        let day = DateTime::<Utc>::from(self.clock.now())
+            .format("%Y-%m-%d")
+            .to_string();
+        if let Some(latest) = self.balance_history_dao.history(1).first() {
+            if latest.day == day {
+                return;
+            }
+        }
+        let balances = match self
+            .verify_chain()
+            .map_err(|e| format!("Couldn't use the blockchain service: {}", e))
+            .and_then(|_| self.wallet_balances(&self.earning_wallet))
+        {
+            Ok(balances) => balances,
+            Err(e) => {
+                warning!(
+                    self.logger,
+                    "Couldn't record today's earning wallet balances; will try again later: {}",
+                    e
+                );
+                return;
+            }
+        };
+        let masq_price_opt = match self.price_feed_url_opt.as_ref() {
+            Some(price_feed_url) => match fetch_price(self.price_feed.as_ref(), price_feed_url) {
+                Ok(price) => Some(price),
+                Err(e) => {
+                    warning!(
+                        self.logger,
+                        "Recording today's earning wallet balances without a price: {}",
+                        e
+                    );
+                    None
+                }
+            },
+            None => None,
+        };
+        info!(
+            self.logger,
+            "Recorded the balances of earning wallet {} for {}",
+            Redacted(&balances.address),
+            day
+        );
+        self.balance_history_dao.record(&BalanceSnapshot {
+            day,
+            address: balances.address,
+            gas_balance: balances.gas_balance,
+            masq_balance: balances.masq_balance,
+            masq_price_opt,
+        });
+    }
+
+    fn query_wallet_balances(&mut self) -> Result<UiWalletBalancesResponse, String> {
+        self.verify_chain()
+            .map_err(|e| format!("Couldn't use the blockchain service: {}", e))?;
+        let consuming_wallet_opt = match self.consuming_wallet.as_ref() {
+            Some(wallet) => Some(self.wallet_balances(wallet)?),
+            None => None,
+        };
+        Ok(UiWalletBalancesResponse {
+            can_consume: consuming_wallet_opt.is_some(),
+            consuming_wallet_opt,
+            earning_wallet: self.wallet_balances(&self.earning_wallet)?,
+            age: 0,
+        })
+    }
+
+    // Until the blockchain service has said it's for the chain in --chain, nothing goes through it.
+    // A query that fails forgets the answer, so that whichever service answers after the trouble is
+    // asked again.
+    fn verify_chain(&mut self) -> BlockchainResult<()> {
+        if self.chain_verified {
+            return Ok(());
+        }
+        match self.blockchain_interface.verify_chain_id() {
+            Ok(()) => {
+                self.chain_verified = true;
+                Ok(())
+            }
+            Err(BlockchainError::WrongChain {
+                configured,
+                reported,
+            }) => {
+                error!(
+                    self.logger,
+                    "Refusing to use the blockchain service: it's for chain ID {}, but this Node is on {} (chain ID {}). Point --blockchain-service-url at a {} service and restart.",
+                    reported,
+                    chain_name_from_id(configured),
+                    configured,
+                    chain_name_from_id(configured)
+                );
+                Err(BlockchainError::WrongChain {
+                    configured,
+                    reported,
+                })
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    fn wallet_balances(&self, wallet: &Wallet) -> Result<UiWalletBalances, String> {
+        let (gas_balance, masq_balance) = self.blockchain_interface.get_balances(wallet);
+        let failure = |e: BlockchainError| format!("Couldn't get balances of {}: {}", wallet, e);
+        Ok(UiWalletBalances {
+            address: wallet.to_string(),
+            gas_balance: gas_balance.map_err(failure)?.to_string(),
+            masq_balance: masq_balance.map_err(failure)?.to_string(),
+        })
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::accountant::payable_dao::PayableAccount;
+    use crate::accountant::test_utils::PaymentJournalMock;
     use crate::blockchain::bip32::Bip32ECKeyPair;
     use crate::blockchain::blockchain_interface::{
         contract_address, Balance, BlockchainError, BlockchainResult, Nonce, Transaction,
         Transactions,
     };
+    use crate::blockchain::test_utils::{BalanceHistoryDaoMock, FaucetClientMock, PriceFeedMock};
     use crate::test_utils::logging::init_test_logging;
     use crate::test_utils::logging::TestLogHandler;
     use crate::test_utils::persistent_configuration_mock::PersistentConfigurationMock;
-    use crate::test_utils::recorder::peer_actors_builder;
+    use crate::test_utils::recorder::{make_recorder, peer_actors_builder};
+    use crate::test_utils::virtual_clock::VirtualClock;
     use crate::test_utils::{
         make_default_persistent_configuration, make_paying_wallet, make_wallet,
     };
@@ -196,7 +523,7 @@ mod tests {
     use rustc_hex::FromHex;
     use std::cell::RefCell;
     use std::sync::{Arc, Mutex};
-    use std::time::{Duration, SystemTime};
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
     use web3::types::{Address, H256, U256};
 
     fn stub_bi() -> Box<dyn BlockchainInterface> {
@@ -215,6 +542,7 @@ mod tests {
             &bc_from_wallet(Some(consuming_wallet.clone())),
             stub_bi(),
             Box::new(make_default_persistent_configuration()),
+            Box::new(BalanceHistoryDaoMock::default()),
         );
 
         let system = System::new("blockchain_bridge_receives_bind_message");
@@ -241,6 +569,7 @@ mod tests {
             &bc_from_wallet(None),
             stub_bi(),
             Box::new(PersistentConfigurationMock::default()),
+            Box::new(BalanceHistoryDaoMock::default()),
         );
 
         let system = System::new("blockchain_bridge_receives_bind_message");
@@ -267,6 +596,11 @@ mod tests {
         pub contract_address_results: RefCell<Vec<Address>>,
         pub get_transaction_count_parameters: Arc<Mutex<Vec<Wallet>>>,
         pub get_transaction_count_results: RefCell<Vec<BlockchainResult<U256>>>,
+        pub get_eth_balance_parameters: Arc<Mutex<Vec<Wallet>>>,
+        pub get_eth_balance_results: RefCell<Vec<Balance>>,
+        pub get_token_balance_parameters: Arc<Mutex<Vec<Wallet>>>,
+        pub get_token_balance_results: RefCell<Vec<Balance>>,
+        pub verify_chain_id_results: RefCell<Vec<BlockchainResult<()>>>,
     }
 
     impl BlockchainInterfaceMock {
@@ -292,6 +626,31 @@ mod tests {
             self.get_transaction_count_results.borrow_mut().push(result);
             self
         }
+
+        fn get_eth_balance_params(mut self, params: &Arc<Mutex<Vec<Wallet>>>) -> Self {
+            self.get_eth_balance_parameters = params.clone();
+            self
+        }
+
+        fn get_eth_balance_result(self, result: Balance) -> Self {
+            self.get_eth_balance_results.borrow_mut().push(result);
+            self
+        }
+
+        fn get_token_balance_params(mut self, params: &Arc<Mutex<Vec<Wallet>>>) -> Self {
+            self.get_token_balance_parameters = params.clone();
+            self
+        }
+
+        fn get_token_balance_result(self, result: Balance) -> Self {
+            self.get_token_balance_results.borrow_mut().push(result);
+            self
+        }
+
+        fn verify_chain_id_result(self, result: BlockchainResult<()>) -> Self {
+            self.verify_chain_id_results.borrow_mut().push(result);
+            self
+        }
     }
 
     impl BlockchainInterface for BlockchainInterfaceMock {
@@ -299,6 +658,16 @@ mod tests {
             self.contract_address_results.borrow_mut().remove(0)
         }
 
+        // The right chain, unless told otherwise
+        fn verify_chain_id(&self) -> BlockchainResult<()> {
+            let mut results = self.verify_chain_id_results.borrow_mut();
+            if results.is_empty() {
+                Ok(())
+            } else {
+                results.remove(0)
+            }
+        }
+
         fn retrieve_transactions(&self, start_block: u64, recipient: &Wallet) -> Transactions {
             self.retrieve_transactions_parameters
                 .lock()
@@ -325,12 +694,20 @@ mod tests {
             self.send_transaction_results.borrow_mut().remove(0)
         }
 
-        fn get_eth_balance(&self, _address: &Wallet) -> Balance {
-            unimplemented!()
+        fn get_eth_balance(&self, address: &Wallet) -> Balance {
+            self.get_eth_balance_parameters
+                .lock()
+                .unwrap()
+                .push(address.clone());
+            self.get_eth_balance_results.borrow_mut().remove(0)
         }
 
-        fn get_token_balance(&self, _address: &Wallet) -> Balance {
-            unimplemented!()
+        fn get_token_balance(&self, address: &Wallet) -> Balance {
+            self.get_token_balance_parameters
+                .lock()
+                .unwrap()
+                .push(address.clone());
+            self.get_token_balance_results.borrow_mut().remove(0)
         }
 
         fn get_transaction_count(&self, wallet: &Wallet) -> Nonce {
@@ -350,6 +727,8 @@ mod tests {
             block_number: 42u64,
             from: make_wallet("some_address"),
             gwei_amount: 21,
+            transaction_hash: H256::from([12; 32]),
+            confirmations: 1,
         }];
         let result = Ok(expected_results.clone());
         let wallet = make_wallet("smelly");
@@ -363,6 +742,7 @@ mod tests {
             &bc_from_wallet(None),
             Box::new(blockchain_interface_mock),
             Box::new(PersistentConfigurationMock::default()),
+            Box::new(BalanceHistoryDaoMock::default()),
         );
         let addr: Addr<BlockchainBridge> = subject.start();
 
@@ -401,12 +781,16 @@ mod tests {
         let persistent_configuration_mock =
             PersistentConfigurationMock::default().gas_price_result(Ok(Some(expected_gas_price)));
 
+        let record_sent_params_arc = Arc::new(Mutex::new(vec![]));
         let consuming_wallet = make_paying_wallet(b"somewallet");
-        let subject = BlockchainBridge::new(
+        let mut subject = BlockchainBridge::new(
             &bc_from_wallet(Some(consuming_wallet.clone())),
             Box::new(blockchain_interface_mock),
             Box::new(persistent_configuration_mock),
+            Box::new(BalanceHistoryDaoMock::default()),
         );
+        subject.payment_journal =
+            Box::new(PaymentJournalMock::new().record_sent_params(&record_sent_params_arc));
         let addr: Addr<BlockchainBridge> = subject.start();
 
         let request = addr.send(ReportAccountsPayable {
@@ -498,7 +882,12 @@ mod tests {
             expected_payment_1.timestamp = one.timestamp
         }
 
-        assert_eq!(result[1], Ok(expected_payment_1));
+        assert_eq!(result[1], Ok(expected_payment_1.clone()));
+        let record_sent_params = record_sent_params_arc.lock().unwrap();
+        assert_eq!(
+            *record_sent_params,
+            vec![expected_payment_0, expected_payment_1]
+        );
 
         assert_eq!(
             transaction_count_parameters.lock().unwrap()[0],
@@ -510,6 +899,42 @@ mod tests {
         );
     }
 
+    #[test]
+    fn report_accounts_payable_stamps_payments_with_the_time_on_the_bridges_clock() {
+        let system = System::new(
+            "report_accounts_payable_stamps_payments_with_the_time_on_the_bridges_clock",
+        );
+        let blockchain_interface_mock = BlockchainInterfaceMock::default()
+            .get_transaction_count_result(Ok(U256::from(1)))
+            .send_transaction_result(Ok(H256::from("sometransactionhash".keccak256())));
+        let persistent_configuration_mock =
+            PersistentConfigurationMock::default().gas_price_result(Ok(Some(5u64)));
+        let mut subject = BlockchainBridge::new(
+            &bc_from_wallet(Some(make_paying_wallet(b"somewallet"))),
+            Box::new(blockchain_interface_mock),
+            Box::new(persistent_configuration_mock),
+            Box::new(BalanceHistoryDaoMock::default()),
+        );
+        subject.payment_journal = Box::new(PaymentJournalMock::new());
+        let then = SystemTime::UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+        subject.set_clock(Arc::new(VirtualClock::new(then)));
+        let addr: Addr<BlockchainBridge> = subject.start();
+
+        let request = addr.send(ReportAccountsPayable {
+            accounts: vec![PayableAccount {
+                wallet: make_wallet("blah"),
+                balance: 42,
+                last_paid_timestamp: then,
+                pending_payment_transaction: None,
+            }],
+        });
+        System::current().stop();
+        system.run();
+
+        let result = request.wait().unwrap().unwrap();
+        assert_eq!(result[0].clone().unwrap().timestamp, then);
+    }
+
     #[test]
     fn report_accounts_payable_returns_error_for_blockchain_error() {
         let system = System::new("report_accounts_payable_returns_error_for_blockchain_error");
@@ -532,6 +957,7 @@ mod tests {
             &bc_from_wallet(Some(consuming_wallet.clone())),
             Box::new(blockchain_interface_mock),
             Box::new(persistent_configuration_mock),
+            Box::new(BalanceHistoryDaoMock::default()),
         );
         let addr: Addr<BlockchainBridge> = subject.start();
 
@@ -571,6 +997,7 @@ mod tests {
             &BootstrapperConfig::new(),
             Box::new(blockchain_interface_mock),
             Box::new(persistent_configuration_mock),
+            Box::new(BalanceHistoryDaoMock::default()),
         );
         let addr: Addr<BlockchainBridge> = subject.start();
 
@@ -591,6 +1018,498 @@ mod tests {
         assert_eq!(result, &Err("No consuming wallet specified".to_string()));
     }
 
+    #[test]
+    fn a_blockchain_service_for_another_chain_is_refused_for_payments_and_scans() {
+        init_test_logging();
+        let system =
+            System::new("a_blockchain_service_for_another_chain_is_refused_for_payments_and_scans");
+        let wrong_chain = BlockchainError::WrongChain {
+            configured: 3,
+            reported: 1,
+        };
+        let blockchain_interface_mock = BlockchainInterfaceMock::default()
+            .verify_chain_id_result(Err(wrong_chain.clone()))
+            .verify_chain_id_result(Err(wrong_chain.clone()));
+        let retrieve_transactions_parameters = blockchain_interface_mock
+            .retrieve_transactions_parameters
+            .clone();
+        let send_parameters = blockchain_interface_mock
+            .send_transaction_parameters
+            .clone();
+        let subject = BlockchainBridge::new(
+            &bc_from_wallet(Some(make_paying_wallet(b"somewallet"))),
+            Box::new(blockchain_interface_mock),
+            Box::new(PersistentConfigurationMock::default()),
+            Box::new(BalanceHistoryDaoMock::default()),
+        );
+        let addr: Addr<BlockchainBridge> = subject.start();
+
+        let retrieve_request = addr.send(RetrieveTransactions {
+            start_block: 37,
+            recipient: make_wallet("smelly"),
+        });
+        let payable_request = addr.send(ReportAccountsPayable {
+            accounts: vec![PayableAccount {
+                wallet: make_wallet("blah"),
+                balance: 42,
+                last_paid_timestamp: SystemTime::now(),
+                pending_payment_transaction: None,
+            }],
+        });
+
+        System::current().stop();
+        system.run();
+        assert_eq!(retrieve_request.wait().unwrap(), Err(wrong_chain.clone()));
+        assert_eq!(
+            payable_request.wait().unwrap(),
+            Err(wrong_chain.to_string())
+        );
+        assert!(retrieve_transactions_parameters.lock().unwrap().is_empty());
+        assert!(send_parameters.lock().unwrap().is_empty());
+        TestLogHandler::new().exists_log_containing(
+            "ERROR: BlockchainBridge: Refusing to use the blockchain service: it's for chain ID 1, \
+             but this Node is on ropsten (chain ID 3). Point --blockchain-service-url at a ropsten \
+             service and restart.",
+        );
+    }
+
+    #[test]
+    fn the_chain_is_verified_once_and_again_after_a_query_fails() {
+        let system = System::new("the_chain_is_verified_once_and_again_after_a_query_fails");
+        let wrong_chain = BlockchainError::WrongChain {
+            configured: 3,
+            reported: 1,
+        };
+        let blockchain_interface_mock = BlockchainInterfaceMock::default()
+            .verify_chain_id_result(Ok(()))
+            .verify_chain_id_result(Err(wrong_chain.clone()))
+            .retrieve_transactions_result(Ok(vec![]))
+            .retrieve_transactions_result(Err(BlockchainError::QueryFailed));
+        let subject = BlockchainBridge::new(
+            &bc_from_wallet(None),
+            Box::new(blockchain_interface_mock),
+            Box::new(PersistentConfigurationMock::default()),
+            Box::new(BalanceHistoryDaoMock::default()),
+        );
+        let addr: Addr<BlockchainBridge> = subject.start();
+        let retrieve = || {
+            addr.send(RetrieveTransactions {
+                start_block: 37,
+                recipient: make_wallet("smelly"),
+            })
+        };
+
+        let first = retrieve();
+        let second = retrieve();
+        let third = retrieve();
+
+        System::current().stop();
+        system.run();
+        assert_eq!(first.wait().unwrap(), Ok(vec![]));
+        assert_eq!(second.wait().unwrap(), Err(BlockchainError::QueryFailed));
+        assert_eq!(third.wait().unwrap(), Err(wrong_chain));
+    }
+
+    #[test]
+    fn wallet_balances_request_reports_both_wallets() {
+        let get_eth_balance_params_arc = Arc::new(Mutex::new(vec![]));
+        let get_token_balance_params_arc = Arc::new(Mutex::new(vec![]));
+        let blockchain_interface = BlockchainInterfaceMock::default()
+            .get_eth_balance_params(&get_eth_balance_params_arc)
+            .get_eth_balance_result(Ok(U256::from(1_000_000_000_000_000_000u64)))
+            .get_eth_balance_result(Ok(U256::from(2)))
+            .get_token_balance_params(&get_token_balance_params_arc)
+            .get_token_balance_result(Ok(U256::from(3)))
+            .get_token_balance_result(Ok(U256::from(4)));
+        let consuming_wallet = make_paying_wallet(b"consuming");
+        let earning_wallet = make_wallet("earning");
+        let mut config = bc_from_wallet(Some(consuming_wallet.clone()));
+        config.earning_wallet = earning_wallet.clone();
+        let subject = BlockchainBridge::new(
+            &config,
+            Box::new(blockchain_interface),
+            Box::new(PersistentConfigurationMock::default()),
+            Box::new(BalanceHistoryDaoMock::default()),
+        );
+        let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
+        let system = System::new("test");
+        let addr: Addr<BlockchainBridge> = subject.start();
+        addr.try_send(BindMessage {
+            peer_actors: peer_actors_builder().ui_gateway(ui_gateway).build(),
+        })
+        .unwrap();
+
+        addr.try_send(NodeFromUiMessage {
+            client_id: 1234,
+            body: UiWalletBalancesRequest {}.tmb(2345),
+        })
+        .unwrap();
+
+        System::current().stop();
+        system.run();
+        let ui_gateway_recording = ui_gateway_recording_arc.lock().unwrap();
+        assert_eq!(
+            ui_gateway_recording.get_record::<NodeToUiMessage>(0),
+            &NodeToUiMessage {
+                target: ClientId(1234),
+                body: UiWalletBalancesResponse {
+                    consuming_wallet_opt: Some(UiWalletBalances {
+                        address: consuming_wallet.to_string(),
+                        gas_balance: "1000000000000000000".to_string(),
+                        masq_balance: "3".to_string(),
+                    }),
+                    earning_wallet: UiWalletBalances {
+                        address: earning_wallet.to_string(),
+                        gas_balance: "2".to_string(),
+                        masq_balance: "4".to_string(),
+                    },
+                    can_consume: true,
+                    age: 0,
+                }
+                .tmb(2345),
+            }
+        );
+        assert_eq!(
+            *get_eth_balance_params_arc.lock().unwrap(),
+            vec![consuming_wallet.clone(), earning_wallet.clone()]
+        );
+        assert_eq!(
+            *get_token_balance_params_arc.lock().unwrap(),
+            vec![consuming_wallet, earning_wallet]
+        );
+    }
+
+    #[test]
+    fn request_test_funds_asks_the_faucet_and_reports_what_it_said() {
+        init_test_logging();
+        let post_params_arc = Arc::new(Mutex::new(vec![]));
+        let consuming_wallet = make_paying_wallet(b"consuming");
+        let mut config = bc_from_wallet(Some(consuming_wallet.clone()));
+        config.blockchain_bridge_config.faucet_url_opt =
+            Some("https://faucet.example.com/fund".to_string());
+        let mut subject = BlockchainBridge::new(
+            &config,
+            stub_bi(),
+            Box::new(PersistentConfigurationMock::default()),
+            Box::new(BalanceHistoryDaoMock::default()),
+        );
+        subject.faucet_client = Box::new(
+            FaucetClientMock::default()
+                .post_params(&post_params_arc)
+                .post_result(Ok(br#"{"transaction": "0xfeed"}"#.to_vec()))
+                .post_result(Err("Connection refused".to_string())),
+        );
+        let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
+        let system = System::new("test");
+        let addr: Addr<BlockchainBridge> = subject.start();
+        addr.try_send(BindMessage {
+            peer_actors: peer_actors_builder().ui_gateway(ui_gateway).build(),
+        })
+        .unwrap();
+
+        addr.try_send(NodeFromUiMessage {
+            client_id: 1234,
+            body: UiRequestTestFundsRequest {}.tmb(1),
+        })
+        .unwrap();
+        addr.try_send(NodeFromUiMessage {
+            client_id: 1234,
+            body: UiRequestTestFundsRequest {}.tmb(2),
+        })
+        .unwrap();
+
+        System::current().stop();
+        system.run();
+        let ui_gateway_recording = ui_gateway_recording_arc.lock().unwrap();
+        assert_eq!(
+            ui_gateway_recording.get_record::<NodeToUiMessage>(0),
+            &NodeToUiMessage {
+                target: ClientId(1234),
+                body: UiRequestTestFundsResponse {
+                    address: consuming_wallet.to_string(),
+                    transaction: "0xfeed".to_string(),
+                }
+                .tmb(1),
+            }
+        );
+        let failure =
+            "Couldn't ask https://faucet.example.com/fund for test funds: Connection refused";
+        assert_eq!(
+            ui_gateway_recording.get_record::<NodeToUiMessage>(1),
+            &NodeToUiMessage {
+                target: ClientId(1234),
+                body: MessageBody {
+                    opcode: "requestTestFunds".to_string(),
+                    path: Conversation(2),
+                    payload: Err((TEST_FUNDS_ERROR, failure.to_string())),
+                },
+            }
+        );
+        assert_eq!(post_params_arc.lock().unwrap().len(), 2);
+        TestLogHandler::new()
+            .exists_log_containing(&format!("WARN: BlockchainBridge: {}", failure));
+    }
+
+    #[test]
+    fn wallet_balances_are_answered_from_the_cache_until_it_expires() {
+        let get_eth_balance_params_arc = Arc::new(Mutex::new(vec![]));
+        let blockchain_interface = BlockchainInterfaceMock::default()
+            .get_eth_balance_params(&get_eth_balance_params_arc)
+            .get_eth_balance_result(Ok(U256::from(5)))
+            .get_token_balance_result(Ok(U256::from(6)));
+        let mut subject = BlockchainBridge::new(
+            &bc_from_wallet(None),
+            Box::new(blockchain_interface),
+            Box::new(PersistentConfigurationMock::default()),
+            Box::new(BalanceHistoryDaoMock::default()),
+        );
+        let cached = UiWalletBalancesResponse {
+            consuming_wallet_opt: None,
+            earning_wallet: UiWalletBalances {
+                address: "0xcached".to_string(),
+                gas_balance: "1".to_string(),
+                masq_balance: "2".to_string(),
+            },
+            can_consume: false,
+            age: 0,
+        };
+        let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
+        let system = System::new("test");
+        subject.ui_message_sub = Some(ui_gateway.start().recipient());
+        subject.balance_cache_opt = Some((
+            SystemTime::now() - Duration::from_secs(30),
+            Ok(cached.clone()),
+        ));
+
+        subject.handle_wallet_balances(1234, 1);
+        subject.balance_cache_opt = Some((
+            SystemTime::now() - BALANCE_CACHE_LIFETIME - Duration::from_secs(1),
+            Ok(cached.clone()),
+        ));
+        subject.handle_wallet_balances(1234, 2);
+
+        System::current().stop();
+        system.run();
+        let ui_gateway_recording = ui_gateway_recording_arc.lock().unwrap();
+        let (first, _) = UiWalletBalancesResponse::fmb(
+            ui_gateway_recording
+                .get_record::<NodeToUiMessage>(0)
+                .body
+                .clone(),
+        )
+        .unwrap();
+        assert_eq!(first.earning_wallet, cached.earning_wallet);
+        assert_eq!(first.age, 30);
+        let (second, _) = UiWalletBalancesResponse::fmb(
+            ui_gateway_recording
+                .get_record::<NodeToUiMessage>(1)
+                .body
+                .clone(),
+        )
+        .unwrap();
+        assert_eq!(second.earning_wallet.gas_balance, "5".to_string());
+        assert_eq!(second.earning_wallet.masq_balance, "6".to_string());
+        assert_eq!(second.age, 0);
+        assert_eq!(get_eth_balance_params_arc.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn wallet_balances_failure_is_reported_and_not_retried_right_away() {
+        init_test_logging();
+        let get_eth_balance_params_arc = Arc::new(Mutex::new(vec![]));
+        let blockchain_interface = BlockchainInterfaceMock::default()
+            .get_eth_balance_params(&get_eth_balance_params_arc)
+            .get_eth_balance_result(Err(BlockchainError::QueryFailed))
+            .get_token_balance_result(Ok(U256::from(6)));
+        let earning_wallet = make_wallet("earning");
+        let mut config = bc_from_wallet(None);
+        config.earning_wallet = earning_wallet.clone();
+        let mut subject = BlockchainBridge::new(
+            &config,
+            Box::new(blockchain_interface),
+            Box::new(PersistentConfigurationMock::default()),
+            Box::new(BalanceHistoryDaoMock::default()),
+        );
+        let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
+        let system = System::new("test");
+        subject.ui_message_sub = Some(ui_gateway.start().recipient());
+
+        subject.handle_wallet_balances(1234, 1);
+        subject.handle_wallet_balances(1234, 2);
+
+        System::current().stop();
+        system.run();
+        let ui_gateway_recording = ui_gateway_recording_arc.lock().unwrap();
+        let expected_message = format!(
+            "Couldn't get balances of {}: Blockchain QueryFailed.",
+            earning_wallet
+        );
+        (0..2).for_each(|index| {
+            let record = ui_gateway_recording.get_record::<NodeToUiMessage>(index);
+            assert_eq!(
+                record.body.payload,
+                Err((BLOCKCHAIN_QUERY_ERROR, expected_message.clone()))
+            );
+            assert_eq!(record.body.opcode, "walletBalances".to_string());
+        });
+        assert_eq!(get_eth_balance_params_arc.lock().unwrap().len(), 1);
+        TestLogHandler::new()
+            .exists_log_containing(&format!("WARN: BlockchainBridge: {}", expected_message));
+    }
+
+    fn make_snapshot(day: &str, masq_price_opt: Option<&str>) -> BalanceSnapshot {
+        BalanceSnapshot {
+            day: day.to_string(),
+            address: make_wallet("earning").to_string(),
+            gas_balance: "5".to_string(),
+            masq_balance: "6".to_string(),
+            masq_price_opt: masq_price_opt.map(|price| price.to_string()),
+        }
+    }
+
+    #[test]
+    fn balance_snapshot_is_recorded_once_a_day_with_the_price_from_the_feed() {
+        let record_params_arc = Arc::new(Mutex::new(vec![]));
+        let fetch_params_arc = Arc::new(Mutex::new(vec![]));
+        let get_eth_balance_params_arc = Arc::new(Mutex::new(vec![]));
+        let blockchain_interface = BlockchainInterfaceMock::default()
+            .get_eth_balance_params(&get_eth_balance_params_arc)
+            .get_eth_balance_result(Ok(U256::from(5)))
+            .get_token_balance_result(Ok(U256::from(6)));
+        let mut config = bc_from_wallet(Some(make_paying_wallet(b"consuming")));
+        config.earning_wallet = make_wallet("earning");
+        config.blockchain_bridge_config.price_feed_url_opt =
+            Some("https://prices.example.com/masq".to_string());
+        let mut subject = BlockchainBridge::new(
+            &config,
+            Box::new(blockchain_interface),
+            Box::new(PersistentConfigurationMock::default()),
+            Box::new(
+                BalanceHistoryDaoMock::default()
+                    .record_params(&record_params_arc)
+                    .history_result(vec![make_snapshot("2020-12-30", None)])
+                    .history_result(vec![make_snapshot("2020-12-31", None)]),
+            ),
+        );
+        subject.price_feed = Box::new(
+            PriceFeedMock::default()
+                .fetch_params(&fetch_params_arc)
+                .fetch_result(Ok(br#"{"price": "0.0421"}"#.to_vec())),
+        );
+        subject.set_clock(Arc::new(VirtualClock::new(
+            UNIX_EPOCH + Duration::from_secs(1_609_416_000),
+        )));
+
+        subject.record_balance_snapshot();
+        subject.record_balance_snapshot();
+
+        assert_eq!(
+            *record_params_arc.lock().unwrap(),
+            vec![make_snapshot("2020-12-31", Some("0.0421"))]
+        );
+        assert_eq!(
+            *get_eth_balance_params_arc.lock().unwrap(),
+            vec![make_wallet("earning")]
+        );
+        assert_eq!(
+            *fetch_params_arc.lock().unwrap(),
+            vec!["https://prices.example.com/masq".to_string()]
+        );
+    }
+
+    #[test]
+    fn balance_snapshot_waits_for_balances_but_not_for_a_price() {
+        init_test_logging();
+        let record_params_arc = Arc::new(Mutex::new(vec![]));
+        let blockchain_interface = BlockchainInterfaceMock::default()
+            .get_eth_balance_result(Err(BlockchainError::QueryFailed))
+            .get_token_balance_result(Ok(U256::from(6)))
+            .get_eth_balance_result(Ok(U256::from(5)))
+            .get_token_balance_result(Ok(U256::from(6)));
+        let mut config = bc_from_wallet(None);
+        config.earning_wallet = make_wallet("earning");
+        config.blockchain_bridge_config.price_feed_url_opt =
+            Some("https://prices.example.com/masq".to_string());
+        let mut subject = BlockchainBridge::new(
+            &config,
+            Box::new(blockchain_interface),
+            Box::new(PersistentConfigurationMock::default()),
+            Box::new(BalanceHistoryDaoMock::default().record_params(&record_params_arc)),
+        );
+        subject.price_feed =
+            Box::new(PriceFeedMock::default().fetch_result(Err("Connection refused".to_string())));
+        subject.set_clock(Arc::new(VirtualClock::new(
+            UNIX_EPOCH + Duration::from_secs(1_609_416_000),
+        )));
+
+        subject.record_balance_snapshot();
+        subject.record_balance_snapshot();
+
+        assert_eq!(
+            *record_params_arc.lock().unwrap(),
+            vec![make_snapshot("2020-12-31", None)]
+        );
+        let tlh = TestLogHandler::new();
+        tlh.exists_log_containing(&format!(
+            "WARN: BlockchainBridge: Couldn't record today's earning wallet balances; will try again later: Couldn't get balances of {}",
+            make_wallet("earning")
+        ));
+        tlh.exists_log_containing("WARN: BlockchainBridge: Recording today's earning wallet balances without a price: Couldn't ask https://prices.example.com/masq for the MASQ price: Connection refused");
+    }
+
+    #[test]
+    fn balance_history_request_is_answered_from_the_database() {
+        let history_params_arc = Arc::new(Mutex::new(vec![]));
+        let mut subject = BlockchainBridge::new(
+            &bc_from_wallet(None),
+            stub_bi(),
+            Box::new(PersistentConfigurationMock::default()),
+            Box::new(
+                BalanceHistoryDaoMock::default()
+                    .history_params(&history_params_arc)
+                    .history_result(vec![
+                        make_snapshot("2020-12-31", None),
+                        make_snapshot("2020-12-30", Some("0.0421")),
+                    ]),
+            ),
+        );
+        let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
+        let system = System::new("test");
+        subject.ui_message_sub = Some(ui_gateway.start().recipient());
+
+        subject.handle_balance_history(1234, 4321, UiBalanceHistoryRequest { days: 365 });
+
+        System::current().stop();
+        system.run();
+        let ui_gateway_recording = ui_gateway_recording_arc.lock().unwrap();
+        let response = ui_gateway_recording.get_record::<NodeToUiMessage>(0);
+        assert_eq!(response.target, ClientId(1234));
+        let (payload, context_id) = UiBalanceHistoryResponse::fmb(response.body.clone()).unwrap();
+        assert_eq!(context_id, 4321);
+        assert_eq!(
+            payload,
+            UiBalanceHistoryResponse {
+                days: vec![
+                    UiBalanceDay {
+                        date: "2020-12-31".to_string(),
+                        address: make_wallet("earning").to_string(),
+                        gas_balance: "5".to_string(),
+                        masq_balance: "6".to_string(),
+                        masq_price_opt: None,
+                    },
+                    UiBalanceDay {
+                        date: "2020-12-30".to_string(),
+                        address: make_wallet("earning").to_string(),
+                        gas_balance: "5".to_string(),
+                        masq_balance: "6".to_string(),
+                        masq_price_opt: Some("0.0421".to_string()),
+                    },
+                ]
+            }
+        );
+        assert_eq!(*history_params_arc.lock().unwrap(), vec![365]);
+    }
+
     #[test]
     fn cant_be_crashed_if_key_doesnt_match() {
         let system = System::new("test");
@@ -600,6 +1519,7 @@ mod tests {
             &config,
             Box::new(BlockchainInterfaceMock::default()),
             Box::new(PersistentConfigurationMock::default()),
+            Box::new(BalanceHistoryDaoMock::default()),
         );
         let addr: Addr<BlockchainBridge> = subject.start();
 
@@ -622,6 +1542,7 @@ mod tests {
             &BootstrapperConfig::new(),
             Box::new(BlockchainInterfaceMock::default()),
             Box::new(PersistentConfigurationMock::default()),
+            Box::new(BalanceHistoryDaoMock::default()),
         );
         let addr: Addr<BlockchainBridge> = subject.start();
 
@@ -648,6 +1569,7 @@ mod tests {
             &config,
             Box::new(BlockchainInterfaceMock::default()),
             Box::new(PersistentConfigurationMock::default()),
+            Box::new(BalanceHistoryDaoMock::default()),
         );
         let addr: Addr<BlockchainBridge> = subject.start();
 
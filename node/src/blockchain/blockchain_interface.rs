@@ -1,5 +1,6 @@
 // Copyright (c) 2017-2019, Substratum LLC (https://substratum.net) and/or its affiliates. All rights reserved.
 
+use crate::blockchain::payment_adapter::PaymentAdapter;
 use crate::blockchain::raw_transaction::RawTransaction;
 use crate::sub_lib::logger::Logger;
 use crate::sub_lib::wallet::Wallet;
@@ -120,7 +121,11 @@ const TRANSFER_METHOD_ID: [u8; 4] = [0xa9, 0x05, 0x9c, 0xbb];
 pub struct Transaction {
     pub block_number: u64,
     pub from: Wallet,
+    // In the payment adapter's native units, which are Gwei for MASQ
     pub gwei_amount: u64,
+    pub transaction_hash: H256,
+    // Including the block the transaction is in
+    pub confirmations: u64,
 }
 
 impl fmt::Display for Transaction {
@@ -140,6 +145,8 @@ pub enum BlockchainError {
     InvalidResponse,
     QueryFailed,
     TransactionFailed(String),
+    // The blockchain service is for some other chain than the one --chain names
+    WrongChain { configured: u8, reported: u64 },
 }
 
 impl Display for BlockchainError {
@@ -156,6 +163,10 @@ pub type Transactions = BlockchainResult<Vec<Transaction>>;
 pub trait BlockchainInterface {
     fn contract_address(&self) -> Address;
 
+    // Asks the blockchain service which chain it's for, so that a testnet service can't be used to
+    // pay mainnet debts or the other way around
+    fn verify_chain_id(&self) -> BlockchainResult<()>;
+
     fn retrieve_transactions(&self, start_block: u64, recipient: &Wallet) -> Transactions;
 
     fn send_transaction(
@@ -207,6 +218,10 @@ impl BlockchainInterface for BlockchainInterfaceClandestine {
         contract_address(self.chain_id)
     }
 
+    fn verify_chain_id(&self) -> BlockchainResult<()> {
+        Ok(())
+    }
+
     fn retrieve_transactions(&self, _start_block: u64, _recipient: &Wallet) -> Transactions {
         let msg = "Can't retrieve transactions clandestinely yet".to_string();
         error!(self.logger, "{}", &msg);
@@ -248,6 +263,7 @@ pub struct BlockchainInterfaceNonClandestine<T: Transport + Debug> {
     _event_loop_handle: EventLoopHandle,
     web3: Web3<T>,
     contract: Contract<T>,
+    payment_adapter: Box<dyn PaymentAdapter>,
 }
 
 const GWEI: U256 = U256([1_000_000_000u64, 0, 0, 0]);
@@ -266,7 +282,32 @@ where
     T: Transport + Debug,
 {
     fn contract_address(&self) -> Address {
-        contract_address(self.chain_id)
+        self.payment_adapter.token().contract_address
+    }
+
+    // Dev chains choose their own IDs
+    fn verify_chain_id(&self) -> BlockchainResult<()> {
+        if self.chain_id == chain_id_from_name("dev") {
+            return Ok(());
+        }
+        let reported = self
+            .web3
+            .transport()
+            .execute("eth_chainId", vec![])
+            .wait()
+            .map_err(|_| BlockchainError::QueryFailed)?;
+        let reported = reported
+            .as_str()
+            .and_then(|hex| u64::from_str_radix(hex.trim_start_matches("0x"), 16).ok())
+            .ok_or(BlockchainError::InvalidResponse)?;
+        if reported == u64::from(self.chain_id) {
+            Ok(())
+        } else {
+            Err(BlockchainError::WrongChain {
+                configured: self.chain_id,
+                reported,
+            })
+        }
     }
 
     fn retrieve_transactions(&self, start_block: u64, recipient: &Wallet) -> Transactions {
@@ -292,7 +333,8 @@ where
 
         let log_request = self.web3.eth().logs(filter);
         let logger = self.logger.clone();
-        log_request
+        let payment_adapter = &self.payment_adapter;
+        let transactions = log_request
             .then(|logs| {
                 future::result::<Vec<Transaction>, BlockchainError>(match logs {
                     Ok(logs) => {
@@ -304,18 +346,23 @@ where
                         } else {
                             let transactions = logs
                                 .iter()
-                                .filter_map(|log: &Log| match log.block_number {
-                                    Some(block_number) => {
-                                        let amount: U256 = U256::from(log.data.0.as_slice());
-                                        let gwei_amount = to_gwei(amount);
-                                        gwei_amount.map(|gwei_amount| Transaction {
-                                            block_number: u64::try_from(block_number)
-                                                .expect("Internal Error"), // TODO: back to testing for overflow
-                                            from: Wallet::from(log.topics[1]),
-                                            gwei_amount,
-                                        })
+                                .filter_map(|log: &Log| {
+                                    match (log.block_number, log.transaction_hash) {
+                                        (Some(block_number), Some(transaction_hash)) => {
+                                            let amount: U256 = U256::from(log.data.0.as_slice());
+                                            let gwei_amount = payment_adapter.to_native(amount);
+                                            gwei_amount.map(|gwei_amount| Transaction {
+                                                block_number: u64::try_from(block_number)
+                                                    .expect("Internal Error"), // TODO: back to testing for overflow
+                                                from: Wallet::from(log.topics[1]),
+                                                gwei_amount,
+                                                transaction_hash,
+                                                // Counted below, once we know the latest block
+                                                confirmations: 1,
+                                            })
+                                        }
+                                        _ => None,
                                     }
-                                    None => None,
                                 })
                                 .collect();
                             debug!(logger, "Retrieved transactions: {:?}", transactions);
@@ -325,7 +372,25 @@ where
                     Err(_) => Err(BlockchainError::QueryFailed),
                 })
             })
+            .wait()?;
+        if transactions.is_empty() {
+            return Ok(transactions);
+        }
+        let latest_block = self
+            .web3
+            .eth()
+            .block_number()
             .wait()
+            .map_err(|_| BlockchainError::QueryFailed)?;
+        let latest_block =
+            u64::try_from(latest_block).map_err(|_| BlockchainError::InvalidResponse)?;
+        Ok(transactions
+            .into_iter()
+            .map(|transaction| Transaction {
+                confirmations: latest_block.saturating_sub(transaction.block_number) + 1,
+                ..transaction
+            })
+            .collect())
     }
 
     fn send_transaction(
@@ -338,8 +403,9 @@ where
     ) -> BlockchainResult<H256> {
         debug!(
             self.logger,
-            "Sending transaction for {} Gwei to {} from {}: (chain_id: {} contract: {:#x})",
+            "Sending transaction for {} {} units to {} from {}: (chain_id: {} contract: {:#x})",
             amount,
+            self.payment_adapter.token().symbol,
             recipient,
             consuming_wallet,
             self.chain_id,
@@ -348,7 +414,9 @@ where
         let mut data = [0u8; 4 + 32 + 32];
         data[0..4].copy_from_slice(&TRANSFER_METHOD_ID);
         data[16..36].copy_from_slice(&recipient.address().0[..]);
-        to_wei(amount).to_big_endian(&mut data[36..68]);
+        self.payment_adapter
+            .to_token_units(amount)
+            .to_big_endian(&mut data[36..68]);
         let gas_limit = ethereum_types::U256::try_from(
             data.iter()
                 .fold(55_000u64, |acc, v| acc + if v == &0u8 { 4 } else { 68 }),
@@ -419,11 +487,16 @@ impl<T> BlockchainInterfaceNonClandestine<T>
 where
     T: Transport + Debug,
 {
-    pub fn new(transport: T, event_loop_handle: EventLoopHandle, chain_id: u8) -> Self {
+    pub fn new(
+        transport: T,
+        event_loop_handle: EventLoopHandle,
+        chain_id: u8,
+        payment_adapter: Box<dyn PaymentAdapter>,
+    ) -> Self {
         let web3 = Web3::new(transport);
         let contract = Contract::from_json(
             web3.eth(),
-            contract_address(chain_id),
+            payment_adapter.token().contract_address,
             CONTRACT_ABI.as_bytes(),
         )
         .expect("Unable to initialize contract.");
@@ -433,6 +506,7 @@ where
             _event_loop_handle: event_loop_handle,
             web3,
             contract,
+            payment_adapter,
         }
     }
 }
@@ -440,6 +514,7 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::blockchain::payment_adapter::{make_payment_adapter, PaymentToken};
     use crate::sub_lib::wallet::Wallet;
     use crate::test_utils::{make_paying_wallet, make_wallet};
     use ethereum_types::BigEndianHash;
@@ -533,6 +608,9 @@ mod tests {
         thread::spawn(move || {
             Server::new(move |req, mut rsp| {
                 tx.send(req.body().clone()).unwrap();
+                if String::from_utf8_lossy(req.body()).contains("eth_blockNumber") {
+                    return Ok(rsp.body(br#"{"jsonrpc":"2.0","id":4,"result":"0x4be670"}"#.to_vec())?);
+                }
                 Ok(rsp.body(br#"{"jsonrpc":"2.0","id":3,"result":[{"address":"0xcd6c588e005032dd882cd43bf53a32129be81302","blockHash":"0x1a24b9169cbaec3f6effa1f600b70c7ab9e8e86db44062b49132a4415d26732a","blockNumber":"0x4be663","data":"0x0000000000000000000000000000000000000000000000000010000000000000","logIndex":"0x0","removed":false,"topics":["0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef","0x0000000000000000000000003f69f9efd4f2592fd70be8c32ecd9dce71c472fc","0x000000000000000000000000adc1853c7859369639eb414b6342b36288fe6092"],"transactionHash":"0x955cec6ac4f832911ab894ce16aa22c3003f46deff3f7165b32700d2f5ff0681","transactionIndex":"0x0"}]}"#.to_vec())?)
             }).listen(&Ipv4Addr::LOCALHOST.to_string(), &format!("{}", port));
         });
@@ -543,8 +621,12 @@ mod tests {
             port
         ))
         .unwrap();
-        let subject =
-            BlockchainInterfaceNonClandestine::new(transport, event_loop_handle, DEFAULT_CHAIN_ID);
+        let subject = BlockchainInterfaceNonClandestine::new(
+            transport,
+            event_loop_handle,
+            DEFAULT_CHAIN_ID,
+            make_payment_adapter(DEFAULT_CHAIN_ID, None),
+        );
 
         let result = subject
             .retrieve_transactions(
@@ -563,6 +645,11 @@ mod tests {
                 block_number: 4_974_179u64,
                 from: Wallet::from_str("0x3f69f9efd4f2592fd70be8c32ecd9dce71c472fc").unwrap(),
                 gwei_amount: 4_503_599u64,
+                transaction_hash: H256::from_str(
+                    "955cec6ac4f832911ab894ce16aa22c3003f46deff3f7165b32700d2f5ff0681"
+                )
+                .unwrap(),
+                confirmations: 14,
             }],
             result,
         )
@@ -575,8 +662,12 @@ mod tests {
         let port = 8545;
         let (event_loop_handle, transport) =
             Http::new(&format!("http://{}:{}", &Ipv4Addr::LOCALHOST, port)).unwrap();
-        let subject =
-            BlockchainInterfaceNonClandestine::new(transport, event_loop_handle, DEFAULT_CHAIN_ID);
+        let subject = BlockchainInterfaceNonClandestine::new(
+            transport,
+            event_loop_handle,
+            DEFAULT_CHAIN_ID,
+            make_payment_adapter(DEFAULT_CHAIN_ID, None),
+        );
 
         let result = subject
             .retrieve_transactions(42, &Wallet::new("0x3f69f9efd4f2592fd70beecd9dce71c472fc"));
@@ -604,8 +695,12 @@ mod tests {
             port
         ))
         .unwrap();
-        let subject =
-            BlockchainInterfaceNonClandestine::new(transport, event_loop_handle, DEFAULT_CHAIN_ID);
+        let subject = BlockchainInterfaceNonClandestine::new(
+            transport,
+            event_loop_handle,
+            DEFAULT_CHAIN_ID,
+            make_payment_adapter(DEFAULT_CHAIN_ID, None),
+        );
 
         let result = subject.retrieve_transactions(
             42,
@@ -636,8 +731,12 @@ mod tests {
         ))
         .unwrap();
 
-        let subject =
-            BlockchainInterfaceNonClandestine::new(transport, event_loop_handle, DEFAULT_CHAIN_ID);
+        let subject = BlockchainInterfaceNonClandestine::new(
+            transport,
+            event_loop_handle,
+            DEFAULT_CHAIN_ID,
+            make_payment_adapter(DEFAULT_CHAIN_ID, None),
+        );
 
         let result = subject.retrieve_transactions(
             42,
@@ -666,8 +765,12 @@ mod tests {
         ))
         .unwrap();
 
-        let subject =
-            BlockchainInterfaceNonClandestine::new(transport, event_loop_handle, DEFAULT_CHAIN_ID);
+        let subject = BlockchainInterfaceNonClandestine::new(
+            transport,
+            event_loop_handle,
+            DEFAULT_CHAIN_ID,
+            make_payment_adapter(DEFAULT_CHAIN_ID, None),
+        );
 
         let result = subject.retrieve_transactions(
             42,
@@ -677,6 +780,57 @@ mod tests {
         assert_eq!(Ok(vec![]), result);
     }
 
+    fn subject_answering_chain_id(
+        chain_id_hex: &'static str,
+    ) -> BlockchainInterfaceNonClandestine<Http> {
+        let port = find_free_port();
+        thread::spawn(move || {
+            Server::new(move |_req, mut rsp| {
+                Ok(rsp.body(
+                    format!(r#"{{"jsonrpc":"2.0","id":0,"result":"{}"}}"#, chain_id_hex)
+                        .into_bytes(),
+                )?)
+            })
+            .listen(&Ipv4Addr::LOCALHOST.to_string(), &format!("{}", port));
+        });
+        let (event_loop_handle, transport) = Http::new(&format!(
+            "http://{}:{}",
+            &Ipv4Addr::LOCALHOST.to_string(),
+            port
+        ))
+        .unwrap();
+        BlockchainInterfaceNonClandestine::new(
+            transport,
+            event_loop_handle,
+            DEFAULT_CHAIN_ID,
+            make_payment_adapter(DEFAULT_CHAIN_ID, None),
+        )
+    }
+
+    #[test]
+    fn blockchain_interface_non_clandestine_accepts_a_service_for_the_configured_chain() {
+        let subject = subject_answering_chain_id("0x3");
+
+        let result = subject.verify_chain_id();
+
+        assert_eq!(result, Ok(()));
+    }
+
+    #[test]
+    fn blockchain_interface_non_clandestine_refuses_a_service_for_another_chain() {
+        let subject = subject_answering_chain_id("0x1");
+
+        let result = subject.verify_chain_id();
+
+        assert_eq!(
+            result,
+            Err(BlockchainError::WrongChain {
+                configured: DEFAULT_CHAIN_ID,
+                reported: 1
+            })
+        );
+    }
+
     #[test]
     fn blockchain_interface_non_clandestine_can_retrieve_eth_balance_of_a_wallet() {
         let port = find_free_port();
@@ -695,8 +849,12 @@ mod tests {
         ))
         .unwrap();
 
-        let subject =
-            BlockchainInterfaceNonClandestine::new(transport, event_loop_handle, DEFAULT_CHAIN_ID);
+        let subject = BlockchainInterfaceNonClandestine::new(
+            transport,
+            event_loop_handle,
+            DEFAULT_CHAIN_ID,
+            make_payment_adapter(DEFAULT_CHAIN_ID, None),
+        );
 
         let result = subject.get_eth_balance(
             &Wallet::from_str("0x3f69f9efd4f2592fd70be8c32ecd9dce71c472fc").unwrap(),
@@ -717,8 +875,12 @@ mod tests {
         ))
         .unwrap();
 
-        let subject =
-            BlockchainInterfaceNonClandestine::new(transport, event_loop_handle, DEFAULT_CHAIN_ID);
+        let subject = BlockchainInterfaceNonClandestine::new(
+            transport,
+            event_loop_handle,
+            DEFAULT_CHAIN_ID,
+            make_payment_adapter(DEFAULT_CHAIN_ID, None),
+        );
 
         let result =
             subject.get_eth_balance(&Wallet::new("0x3f69f9efd4f2592fd70be8c32ecd9dce71c472fQ"));
@@ -745,8 +907,12 @@ mod tests {
         ))
         .unwrap();
 
-        let subject =
-            BlockchainInterfaceNonClandestine::new(transport, event_loop_handle, DEFAULT_CHAIN_ID);
+        let subject = BlockchainInterfaceNonClandestine::new(
+            transport,
+            event_loop_handle,
+            DEFAULT_CHAIN_ID,
+            make_payment_adapter(DEFAULT_CHAIN_ID, None),
+        );
 
         let result = subject.get_eth_balance(
             &Wallet::from_str("0x3f69f9efd4f2592fd70be8c32ecd9dce71c472fc").unwrap(),
@@ -775,8 +941,12 @@ mod tests {
             port
         ))
         .unwrap();
-        let subject =
-            BlockchainInterfaceNonClandestine::new(transport, event_loop_handle, DEFAULT_CHAIN_ID);
+        let subject = BlockchainInterfaceNonClandestine::new(
+            transport,
+            event_loop_handle,
+            DEFAULT_CHAIN_ID,
+            make_payment_adapter(DEFAULT_CHAIN_ID, None),
+        );
 
         let result = subject.get_token_balance(
             &Wallet::from_str("0x3f69f9efd4f2592fd70be8c32ecd9dce71c472fc").unwrap(),
@@ -796,8 +966,12 @@ mod tests {
             port
         ))
         .unwrap();
-        let subject =
-            BlockchainInterfaceNonClandestine::new(transport, event_loop_handle, DEFAULT_CHAIN_ID);
+        let subject = BlockchainInterfaceNonClandestine::new(
+            transport,
+            event_loop_handle,
+            DEFAULT_CHAIN_ID,
+            make_payment_adapter(DEFAULT_CHAIN_ID, None),
+        );
 
         let result =
             subject.get_token_balance(&Wallet::new("0x3f69f9efd4f2592fd70be8c32ecd9dce71c472fQ"));
@@ -826,8 +1000,12 @@ mod tests {
             port
         ))
         .unwrap();
-        let subject =
-            BlockchainInterfaceNonClandestine::new(transport, event_loop_handle, DEFAULT_CHAIN_ID);
+        let subject = BlockchainInterfaceNonClandestine::new(
+            transport,
+            event_loop_handle,
+            DEFAULT_CHAIN_ID,
+            make_payment_adapter(DEFAULT_CHAIN_ID, None),
+        );
 
         let result = subject.get_token_balance(
             &Wallet::from_str("0x3f69f9efd4f2592fd70be8c32ecd9dce71c472fc").unwrap(),
@@ -856,8 +1034,12 @@ mod tests {
             port
         ))
         .unwrap();
-        let subject =
-            BlockchainInterfaceNonClandestine::new(transport, event_loop_handle, DEFAULT_CHAIN_ID);
+        let subject = BlockchainInterfaceNonClandestine::new(
+            transport,
+            event_loop_handle,
+            DEFAULT_CHAIN_ID,
+            make_payment_adapter(DEFAULT_CHAIN_ID, None),
+        );
 
         let results = subject
             .get_balances(&Wallet::from_str("0x3f69f9efd4f2592fd70be8c32ecd9dce71c472fc").unwrap());
@@ -880,6 +1062,7 @@ mod tests {
             transport.clone(),
             make_fake_event_loop_handle(),
             DEFAULT_CHAIN_ID,
+            make_payment_adapter(DEFAULT_CHAIN_ID, None),
         );
 
         let result = subject.send_transaction(
@@ -895,6 +1078,22 @@ mod tests {
         assert_eq!(result, Ok(H256::from_uint(&U256::from(1))));
     }
 
+    #[test]
+    fn blockchain_interface_non_clandestine_settles_in_the_payment_adapters_token() {
+        let token =
+            PaymentToken::from_str("USDC:0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48:6").unwrap();
+
+        let subject = BlockchainInterfaceNonClandestine::new(
+            TestTransport::default(),
+            make_fake_event_loop_handle(),
+            DEFAULT_CHAIN_ID,
+            make_payment_adapter(DEFAULT_CHAIN_ID, Some(&token)),
+        );
+
+        assert_eq!(subject.contract_address(), token.contract_address);
+        assert_eq!(subject.contract.address(), token.contract_address);
+    }
+
     #[test]
     fn blockchain_interface_non_clandestine_can_fetch_nonce() {
         let mut transport = TestTransport::default();
@@ -907,6 +1106,7 @@ mod tests {
             transport.clone(),
             make_fake_event_loop_handle(),
             DEFAULT_CHAIN_ID,
+            make_payment_adapter(DEFAULT_CHAIN_ID, None),
         );
 
         let result = subject.get_transaction_count(&make_paying_wallet(b"gdasgsa"));
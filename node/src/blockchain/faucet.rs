@@ -0,0 +1,276 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+
+use crate::blockchain::blockchain_interface::chain_name_from_id;
+use crate::bootstrapper::BootstrapperConfig;
+use crate::node_configurator::node_configurator_standard::{app, standard};
+use crate::node_configurator::{initialize_database, RealDirsWrapper};
+use crate::privilege_drop::{PrivilegeDropper, PrivilegeDropperReal};
+use crate::proxy_client::secure_resolver::https_response_body;
+use crate::sub_lib::cli_output;
+use crate::sub_lib::cli_output::OutputFormat;
+use crate::sub_lib::wallet::Wallet;
+use crate::updater::https_post;
+use clap::Arg;
+use masq_lib::command::StdStreams;
+use masq_lib::shared_schema::{output_arg, ConfiguratorError};
+use serde_derive::Deserialize;
+use serde_json::json;
+use std::io;
+use std::io::Read;
+
+pub const REQUEST_TEST_FUNDS_FLAG: &str = "--request-test-funds";
+
+const REQUEST_TEST_FUNDS_HELP: &str =
+    "Ask the faucet at --faucet-url to send test funds to the consuming wallet, print the transaction \
+     it sent them in, and exit without starting the Node. Only for testnets.";
+
+const MAX_FAUCET_RESPONSE_LEN: usize = 65536;
+
+// What a faucet answers with: the hash of the transaction it sent the funds in
+#[derive(Deserialize, Debug)]
+struct FaucetResponse {
+    transaction: String,
+}
+
+pub trait FaucetClient: Send {
+    // Posts {"address": <wallet>, "chain": <chain name>} and returns the response body
+    fn post(&self, faucet_url: &str, request: &[u8]) -> io::Result<Vec<u8>>;
+}
+
+pub struct FaucetClientReal {}
+
+impl FaucetClient for FaucetClientReal {
+    fn post(&self, faucet_url: &str, request: &[u8]) -> io::Result<Vec<u8>> {
+        let mut tls_stream = https_post(faucet_url, "application/json", request)?;
+        let mut response = vec![];
+        let mut buf = [0u8; 4096];
+        loop {
+            let at_eof = match tls_stream.read(&mut buf) {
+                Ok(0) | Err(_) => true,
+                Ok(len) => {
+                    response.extend_from_slice(&buf[..len]);
+                    false
+                }
+            };
+            match https_response_body(&response, at_eof) {
+                Ok(Some(body)) => return Ok(body),
+                Ok(None) if response.len() < MAX_FAUCET_RESPONSE_LEN => (),
+                Ok(None) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "Faucet response too long",
+                    ))
+                }
+                Err(msg) => return Err(io::Error::new(io::ErrorKind::InvalidData, msg)),
+            }
+        }
+    }
+}
+
+// Returns the hash of the transaction that funds the wallet. Mainnet has no faucet, so the request
+// is refused there before anything is sent anywhere.
+pub fn request_test_funds(
+    client: &dyn FaucetClient,
+    faucet_url_opt: Option<&str>,
+    chain_id: u8,
+    consuming_wallet_opt: Option<&Wallet>,
+) -> Result<String, String> {
+    let chain_name = chain_name_from_id(chain_id);
+    if chain_name == "mainnet" {
+        return Err("There are no test funds on mainnet; use --chain with a testnet".to_string());
+    }
+    let faucet_url = faucet_url_opt
+        .ok_or_else(|| "No faucet is configured: give --faucet-url the faucet's URL".to_string())?;
+    let wallet = consuming_wallet_opt.ok_or_else(|| {
+        "There's no consuming wallet to fund: give --consuming-private-key, or a --db-password \
+         that unlocks the one in the database"
+            .to_string()
+    })?;
+    let request = json!({ "address": wallet.to_string(), "chain": chain_name }).to_string();
+    let body = client
+        .post(faucet_url, request.as_bytes())
+        .map_err(|e| format!("Couldn't ask {} for test funds: {}", faucet_url, e))?;
+    match serde_json::from_slice::<FaucetResponse>(&body) {
+        Ok(response) if !response.transaction.is_empty() => Ok(response.transaction),
+        _ => Err(format!(
+            "{} answered with something other than a transaction: {}",
+            faucet_url,
+            String::from_utf8_lossy(&body)
+        )),
+    }
+}
+
+pub fn request_test_funds_mode(
+    args: &[String],
+    streams: &mut StdStreams,
+) -> Result<i32, ConfiguratorError> {
+    let app = app()
+        .arg(
+            Arg::with_name("request-test-funds")
+                .long("request-test-funds")
+                .required(true)
+                .takes_value(false)
+                .help(REQUEST_TEST_FUNDS_HELP),
+        )
+        .arg(output_arg());
+    let dirs_wrapper = RealDirsWrapper {};
+    let multi_config =
+        standard::make_service_mode_multi_config(&dirs_wrapper, &app, args, streams)?;
+    let mut config = BootstrapperConfig::new();
+    standard::privileged_parse_args(&dirs_wrapper, &multi_config, &mut config, streams)?;
+    PrivilegeDropperReal::new().drop_privileges(&config.real_user);
+    let mut persistent_config = initialize_database(
+        &config.data_directory,
+        config.blockchain_bridge_config.chain_id,
+    )?;
+    standard::get_wallets(
+        streams,
+        &multi_config,
+        persistent_config.as_mut(),
+        &mut config,
+    )?;
+    let output_format = OutputFormat::from_args(args);
+    match request_test_funds(
+        &FaucetClientReal {},
+        config.blockchain_bridge_config.faucet_url_opt.as_deref(),
+        config.blockchain_bridge_config.chain_id,
+        config.consuming_wallet.as_ref(),
+    ) {
+        Ok(transaction) => {
+            let address = config
+                .consuming_wallet
+                .as_ref()
+                .expect("Consuming wallet disappeared")
+                .to_string();
+            match output_format {
+                OutputFormat::Human => writeln!(
+                    streams.stdout,
+                    "Test funds are on their way to {} in transaction {}",
+                    address, transaction
+                )
+                .expect("writeln! failed"),
+                OutputFormat::Json => cli_output::write(
+                    streams.stdout,
+                    &cli_output::success(
+                        "requestTestFunds",
+                        json!({ "address": address, "transaction": transaction }),
+                    ),
+                ),
+            }
+            Ok(0)
+        }
+        Err(message) => {
+            match output_format {
+                OutputFormat::Human => {
+                    writeln!(streams.stderr, "{}", message).expect("writeln! failed")
+                }
+                OutputFormat::Json => cli_output::write(
+                    streams.stdout,
+                    &cli_output::failure("requestTestFunds", "faucet", &message, None),
+                ),
+            }
+            Ok(1)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::test_utils::FaucetClientMock;
+    use masq_lib::test_utils::utils::DEFAULT_CHAIN_ID;
+    use std::sync::{Arc, Mutex};
+
+    fn wallet() -> Wallet {
+        Wallet::new("0xcafedeadbeefbabefacecafedeadbeefbabeface")
+    }
+
+    #[test]
+    fn the_faucet_is_asked_to_fund_the_consuming_wallet() {
+        let post_params_arc = Arc::new(Mutex::new(vec![]));
+        let client = FaucetClientMock::default()
+            .post_params(&post_params_arc)
+            .post_result(Ok(br#"{"transaction": "0xabcdef", "amount": "1"}"#.to_vec()));
+
+        let result = request_test_funds(
+            &client,
+            Some("https://faucet.example.com/fund"),
+            DEFAULT_CHAIN_ID,
+            Some(&wallet()),
+        );
+
+        assert_eq!(result, Ok("0xabcdef".to_string()));
+        let post_params = post_params_arc.lock().unwrap();
+        assert_eq!(
+            post_params[0].0,
+            "https://faucet.example.com/fund".to_string()
+        );
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&post_params[0].1).unwrap(),
+            json!({
+                "address": "0xcafedeadbeefbabefacecafedeadbeefbabeface",
+                "chain": chain_name_from_id(DEFAULT_CHAIN_ID),
+            })
+        );
+    }
+
+    #[test]
+    fn nothing_is_asked_without_a_testnet_a_faucet_and_a_wallet() {
+        let client = FaucetClientMock::default();
+
+        let mainnet = request_test_funds(
+            &client,
+            Some("https://faucet.example.com/fund"),
+            1,
+            Some(&wallet()),
+        );
+        let no_faucet = request_test_funds(&client, None, DEFAULT_CHAIN_ID, Some(&wallet()));
+        let no_wallet = request_test_funds(
+            &client,
+            Some("https://faucet.example.com/fund"),
+            DEFAULT_CHAIN_ID,
+            None,
+        );
+
+        assert_eq!(
+            mainnet,
+            Err("There are no test funds on mainnet; use --chain with a testnet".to_string())
+        );
+        assert_eq!(
+            no_faucet,
+            Err("No faucet is configured: give --faucet-url the faucet's URL".to_string())
+        );
+        assert!(no_wallet
+            .unwrap_err()
+            .starts_with("There's no consuming wallet to fund"));
+    }
+
+    #[test]
+    fn faucet_failures_and_odd_answers_are_reported() {
+        let client = FaucetClientMock::default()
+            .post_result(Err(
+                "HTTPS server responded 'HTTP/1.1 429 Too Many Requests'".to_string(),
+            ))
+            .post_result(Ok(b"{\"error\": \"slow down\"}".to_vec()));
+        let ask = || {
+            request_test_funds(
+                &client,
+                Some("https://faucet.example.com/fund"),
+                DEFAULT_CHAIN_ID,
+                Some(&wallet()),
+            )
+        };
+
+        let failed = ask();
+        let odd = ask();
+
+        assert_eq!(
+            failed,
+            Err("Couldn't ask https://faucet.example.com/fund for test funds: HTTPS server responded 'HTTP/1.1 429 Too Many Requests'".to_string())
+        );
+        assert_eq!(
+            odd,
+            Err("https://faucet.example.com/fund answered with something other than a transaction: {\"error\": \"slow down\"}".to_string())
+        );
+    }
+}
@@ -1,9 +1,13 @@
 // Copyright (c) 2017-2019, Substratum LLC (https://substratum.net) and/or its affiliates. All rights reserved.
+pub mod balance_history;
 pub mod bip32;
 pub mod bip39;
 pub mod blockchain_bridge;
 pub mod blockchain_interface;
+pub mod faucet;
 pub mod payer;
+pub mod payment_adapter;
+pub mod price_feed;
 pub mod raw_transaction;
 pub mod signature;
 
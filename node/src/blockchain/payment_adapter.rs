@@ -0,0 +1,230 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use crate::blockchain::blockchain_interface::{chain_name_from_id, contract_address};
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use web3::types::{Address, U256};
+
+pub const MASQ_DECIMALS: u8 = 18;
+// The Accountant has always counted MASQ in Gwei: nine of its eighteen decimals
+pub const MAX_NATIVE_DECIMALS: u8 = 9;
+const MAX_TOKEN_DECIMALS: u8 = 36;
+
+// An ERC-20 token that a mesh settles its debts in: <symbol>:<contract address>:<decimals>
+#[derive(Clone, Debug, PartialEq)]
+pub struct PaymentToken {
+    pub symbol: String,
+    pub contract_address: Address,
+    pub decimals: u8,
+}
+
+impl Display for PaymentToken {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}:{:#x}:{}",
+            self.symbol, self.contract_address, self.decimals
+        )
+    }
+}
+
+impl FromStr for PaymentToken {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let pieces: Vec<&str> = s.split(':').collect();
+        let (symbol, address, decimals) = match pieces.as_slice() {
+            [symbol, address, decimals] => (*symbol, *address, *decimals),
+            _ => {
+                return Err(format!(
+                    "Should be <symbol>:<contract address>:<decimals>, not '{}'",
+                    s
+                ))
+            }
+        };
+        if symbol.is_empty()
+            || symbol.len() > 11
+            || !symbol.chars().all(|c| c.is_ascii_alphanumeric())
+        {
+            return Err(format!(
+                "Token symbol should be 1 to 11 letters and digits, not '{}'",
+                symbol
+            ));
+        }
+        let hex = address.trim_start_matches("0x");
+        let contract_address = match Address::from_str(hex) {
+            Ok(contract_address) if hex.len() == 40 => contract_address,
+            _ => return Err(format!(
+                "Token contract address should be 0x followed by 40 hexadecimal digits, not '{}'",
+                address
+            )),
+        };
+        let decimals = match decimals.parse::<u8>() {
+            Ok(decimals) if decimals <= MAX_TOKEN_DECIMALS => decimals,
+            _ => {
+                return Err(format!(
+                    "Token decimals should be a number from 0 to {}, not '{}'",
+                    MAX_TOKEN_DECIMALS, decimals
+                ))
+            }
+        };
+        Ok(PaymentToken {
+            symbol: symbol.to_string(),
+            contract_address,
+            decimals,
+        })
+    }
+}
+
+// The MASQ token (SHRD on the old testnets) at the chain's own contract
+pub fn default_payment_token(chain_id: u8) -> PaymentToken {
+    let symbol = match chain_name_from_id(chain_id) {
+        "ropsten" | "rinkeby" => "SHRD",
+        _ => "MASQ",
+    };
+    PaymentToken {
+        symbol: symbol.to_string(),
+        contract_address: contract_address(chain_id),
+        decimals: MASQ_DECIMALS,
+    }
+}
+
+// Everything the Node needs to know about the currency it settles in. The Accountant keeps its
+// amounts in native units, which are 10^-native_decimals of a token, so that the same u64
+// columns hold balances in any token without overflowing or losing significant digits.
+pub trait PaymentAdapter: Send {
+    fn token(&self) -> &PaymentToken;
+
+    fn native_decimals(&self) -> u8 {
+        self.token().decimals.min(MAX_NATIVE_DECIMALS)
+    }
+
+    // How many of the token contract's smallest units make one native unit
+    fn native_unit(&self) -> U256 {
+        (self.native_decimals()..self.token().decimals)
+            .fold(U256::one(), |unit, _| unit * U256::from(10))
+    }
+
+    // Truncates amounts smaller than a native unit; None if it's too big to count
+    fn to_native(&self, token_units: U256) -> Option<u64> {
+        let native = token_units / self.native_unit();
+        if native > U256::from(std::u64::MAX) {
+            None
+        } else {
+            Some(native.low_u64())
+        }
+    }
+
+    fn to_token_units(&self, native: u64) -> U256 {
+        U256::from(native) * self.native_unit()
+    }
+}
+
+pub struct Erc20PaymentAdapter {
+    token: PaymentToken,
+}
+
+impl PaymentAdapter for Erc20PaymentAdapter {
+    fn token(&self) -> &PaymentToken {
+        &self.token
+    }
+}
+
+impl Erc20PaymentAdapter {
+    pub fn new(token: PaymentToken) -> Self {
+        Self { token }
+    }
+}
+
+// The token chosen with --payment-token, or the chain's MASQ token
+pub fn make_payment_adapter(
+    chain_id: u8,
+    payment_token_opt: Option<&PaymentToken>,
+) -> Box<dyn PaymentAdapter> {
+    Box::new(Erc20PaymentAdapter::new(
+        payment_token_opt
+            .cloned()
+            .unwrap_or_else(|| default_payment_token(chain_id)),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::blockchain_interface::{chain_id_from_name, to_gwei, to_wei};
+
+    #[test]
+    fn payment_tokens_round_trip_through_strings() {
+        let string = "USDC:0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48:6";
+
+        let result = PaymentToken::from_str(string).unwrap();
+
+        assert_eq!(result.symbol, "USDC".to_string());
+        assert_eq!(result.decimals, 6);
+        assert_eq!(result.to_string(), string.to_string());
+    }
+
+    #[test]
+    fn malformed_payment_tokens_are_explained() {
+        let address = "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48";
+
+        assert_eq!(
+            PaymentToken::from_str("USDC"),
+            Err("Should be <symbol>:<contract address>:<decimals>, not 'USDC'".to_string())
+        );
+        assert_eq!(
+            PaymentToken::from_str(&format!("US-DC:{}:6", address)),
+            Err("Token symbol should be 1 to 11 letters and digits, not 'US-DC'".to_string())
+        );
+        assert_eq!(
+            PaymentToken::from_str("USDC:0x1234:6"),
+            Err(
+                "Token contract address should be 0x followed by 40 hexadecimal digits, not '0x1234'"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            PaymentToken::from_str(&format!("USDC:{}:37", address)),
+            Err("Token decimals should be a number from 0 to 36, not '37'".to_string())
+        );
+    }
+
+    #[test]
+    fn the_default_adapter_counts_masq_in_gwei_as_the_accountant_always_has() {
+        let chain_id = chain_id_from_name("mainnet");
+        let subject = make_payment_adapter(chain_id, None);
+
+        assert_eq!(subject.token(), &default_payment_token(chain_id));
+        assert_eq!(subject.token().symbol, "MASQ".to_string());
+        assert_eq!(subject.token().contract_address, contract_address(chain_id));
+        assert_eq!(subject.native_decimals(), 9);
+        assert_eq!(
+            subject.to_native(U256::from(1_999_999_999)),
+            to_gwei(U256::from(1_999_999_999))
+        );
+        assert_eq!(subject.to_token_units(12345), to_wei(12345));
+    }
+
+    #[test]
+    fn tokens_with_few_decimals_are_counted_in_their_smallest_units() {
+        let token =
+            PaymentToken::from_str("USDC:0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48:6").unwrap();
+        let subject = make_payment_adapter(chain_id_from_name("mainnet"), Some(&token));
+
+        assert_eq!(subject.token(), &token);
+        assert_eq!(subject.native_decimals(), 6);
+        assert_eq!(subject.native_unit(), U256::one());
+        assert_eq!(subject.to_native(U256::from(1_500_000)), Some(1_500_000));
+        assert_eq!(subject.to_token_units(1_500_000), U256::from(1_500_000));
+    }
+
+    #[test]
+    fn amounts_too_big_to_count_natively_are_refused() {
+        let subject = make_payment_adapter(chain_id_from_name("mainnet"), None);
+
+        let result =
+            subject.to_native(subject.to_token_units(std::u64::MAX) + subject.native_unit());
+
+        assert_eq!(result, None);
+    }
+}
@@ -0,0 +1,128 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use crate::proxy_client::secure_resolver::https_response_body;
+use crate::updater::https_get;
+use serde_json::Value;
+use std::io;
+use std::io::Read;
+
+const MAX_PRICE_FEED_RESPONSE_LEN: usize = 65536;
+
+pub trait PriceFeed: Send {
+    // Returns the body of whatever the feed answers a GET with
+    fn fetch(&self, price_feed_url: &str) -> io::Result<Vec<u8>>;
+}
+
+pub struct PriceFeedReal {}
+
+impl PriceFeed for PriceFeedReal {
+    fn fetch(&self, price_feed_url: &str) -> io::Result<Vec<u8>> {
+        let mut tls_stream = https_get(price_feed_url, "application/json")?;
+        let mut response = vec![];
+        let mut buf = [0u8; 4096];
+        loop {
+            let at_eof = match tls_stream.read(&mut buf) {
+                Ok(0) | Err(_) => true,
+                Ok(len) => {
+                    response.extend_from_slice(&buf[..len]);
+                    false
+                }
+            };
+            match https_response_body(&response, at_eof) {
+                Ok(Some(body)) => return Ok(body),
+                Ok(None) if response.len() < MAX_PRICE_FEED_RESPONSE_LEN => (),
+                Ok(None) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "Price feed response too long",
+                    ))
+                }
+                Err(msg) => return Err(io::Error::new(io::ErrorKind::InvalidData, msg)),
+            }
+        }
+    }
+}
+
+// The price of one MASQ token as the feed wrote it, whether as a JSON number or a string, so that
+// no precision is lost on the way into the database
+pub fn fetch_price(feed: &dyn PriceFeed, price_feed_url: &str) -> Result<String, String> {
+    let body = feed
+        .fetch(price_feed_url)
+        .map_err(|e| format!("Couldn't ask {} for the MASQ price: {}", price_feed_url, e))?;
+    let price = match serde_json::from_slice::<Value>(&body) {
+        Ok(Value::Object(map)) => match map.get("price") {
+            Some(Value::Number(number)) => Some(number.to_string()),
+            Some(Value::String(string)) => Some(string.trim().to_string()),
+            _ => None,
+        },
+        _ => None,
+    };
+    match price {
+        Some(price)
+            if price
+                .parse::<f64>()
+                .map(|p| p.is_finite() && p >= 0.0)
+                .unwrap_or(false) =>
+        {
+            Ok(price)
+        }
+        _ => Err(format!(
+            "{} answered with something other than a price: {}",
+            price_feed_url,
+            String::from_utf8_lossy(&body)
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::test_utils::PriceFeedMock;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn prices_are_taken_as_written_whether_numbers_or_strings() {
+        let fetch_params_arc = Arc::new(Mutex::new(vec![]));
+        let feed = PriceFeedMock::default()
+            .fetch_params(&fetch_params_arc)
+            .fetch_result(Ok(br#"{"price": 0.04210, "currency": "USD"}"#.to_vec()))
+            .fetch_result(Ok(br#"{"price": " 0.042100000000000001 "}"#.to_vec()));
+
+        let number = fetch_price(&feed, "https://prices.example.com/masq");
+        let string = fetch_price(&feed, "https://prices.example.com/masq");
+
+        assert_eq!(number, Ok("0.0421".to_string()));
+        assert_eq!(string, Ok("0.042100000000000001".to_string()));
+        assert_eq!(
+            *fetch_params_arc.lock().unwrap(),
+            vec!["https://prices.example.com/masq".to_string(); 2]
+        );
+    }
+
+    #[test]
+    fn feed_failures_and_odd_answers_are_reported() {
+        let feed = PriceFeedMock::default()
+            .fetch_result(Err(
+                "HTTPS server responded 'HTTP/1.1 503 Service Unavailable'".to_string(),
+            ))
+            .fetch_result(Ok(br#"{"price": "cheap"}"#.to_vec()))
+            .fetch_result(Ok(br#"{"price": -1}"#.to_vec()));
+        let ask = || fetch_price(&feed, "https://prices.example.com/masq");
+
+        let failed = ask();
+        let not_a_number = ask();
+        let negative = ask();
+
+        assert_eq!(
+            failed,
+            Err("Couldn't ask https://prices.example.com/masq for the MASQ price: HTTPS server responded 'HTTP/1.1 503 Service Unavailable'".to_string())
+        );
+        assert_eq!(
+            not_a_number,
+            Err("https://prices.example.com/masq answered with something other than a price: {\"price\": \"cheap\"}".to_string())
+        );
+        assert_eq!(
+            negative,
+            Err("https://prices.example.com/masq answered with something other than a price: {\"price\": -1}".to_string())
+        );
+    }
+}
@@ -1,6 +1,11 @@
 // Copyright (c) 2017-2019, Substratum LLC (https://substratum.net) and/or its affiliates. All rights reserved.
 
+use crate::blockchain::balance_history::{BalanceHistoryDao, BalanceSnapshot};
+use crate::blockchain::faucet::FaucetClient;
+use crate::blockchain::price_feed::PriceFeed;
 use bip39::{Language, Mnemonic, Seed};
+use std::io;
+use std::sync::{Arc, Mutex};
 
 pub fn make_meaningless_phrase() -> String {
     "phrase donate agent satoshi burst end company pear obvious achieve depth advice".to_string()
@@ -10,3 +15,107 @@ pub fn make_meaningless_seed() -> Seed {
     let mnemonic = Mnemonic::from_phrase(make_meaningless_phrase(), Language::English).unwrap();
     Seed::new(&mnemonic, "passphrase")
 }
+
+#[derive(Default)]
+pub struct FaucetClientMock {
+    post_params: Arc<Mutex<Vec<(String, String)>>>,
+    post_results: Arc<Mutex<Vec<Result<Vec<u8>, String>>>>,
+}
+
+impl FaucetClient for FaucetClientMock {
+    fn post(&self, faucet_url: &str, request: &[u8]) -> io::Result<Vec<u8>> {
+        self.post_params.lock().unwrap().push((
+            faucet_url.to_string(),
+            String::from_utf8(request.to_vec()).unwrap(),
+        ));
+        self.post_results
+            .lock()
+            .unwrap()
+            .remove(0)
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+    }
+}
+
+impl FaucetClientMock {
+    pub fn post_params(mut self, params: &Arc<Mutex<Vec<(String, String)>>>) -> Self {
+        self.post_params = params.clone();
+        self
+    }
+
+    pub fn post_result(self, result: Result<Vec<u8>, String>) -> Self {
+        self.post_results.lock().unwrap().push(result);
+        self
+    }
+}
+
+#[derive(Default)]
+pub struct PriceFeedMock {
+    fetch_params: Arc<Mutex<Vec<String>>>,
+    fetch_results: Arc<Mutex<Vec<Result<Vec<u8>, String>>>>,
+}
+
+impl PriceFeed for PriceFeedMock {
+    fn fetch(&self, price_feed_url: &str) -> io::Result<Vec<u8>> {
+        self.fetch_params
+            .lock()
+            .unwrap()
+            .push(price_feed_url.to_string());
+        self.fetch_results
+            .lock()
+            .unwrap()
+            .remove(0)
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+    }
+}
+
+impl PriceFeedMock {
+    pub fn fetch_params(mut self, params: &Arc<Mutex<Vec<String>>>) -> Self {
+        self.fetch_params = params.clone();
+        self
+    }
+
+    pub fn fetch_result(self, result: Result<Vec<u8>, String>) -> Self {
+        self.fetch_results.lock().unwrap().push(result);
+        self
+    }
+}
+
+#[derive(Default)]
+pub struct BalanceHistoryDaoMock {
+    record_params: Arc<Mutex<Vec<BalanceSnapshot>>>,
+    history_params: Arc<Mutex<Vec<u64>>>,
+    history_results: Arc<Mutex<Vec<Vec<BalanceSnapshot>>>>,
+}
+
+impl BalanceHistoryDao for BalanceHistoryDaoMock {
+    fn record(&self, snapshot: &BalanceSnapshot) {
+        self.record_params.lock().unwrap().push(snapshot.clone());
+    }
+
+    fn history(&self, days: u64) -> Vec<BalanceSnapshot> {
+        self.history_params.lock().unwrap().push(days);
+        let mut results = self.history_results.lock().unwrap();
+        if results.is_empty() {
+            vec![]
+        } else {
+            results.remove(0)
+        }
+    }
+}
+
+impl BalanceHistoryDaoMock {
+    pub fn record_params(mut self, params: &Arc<Mutex<Vec<BalanceSnapshot>>>) -> Self {
+        self.record_params = params.clone();
+        self
+    }
+
+    pub fn history_params(mut self, params: &Arc<Mutex<Vec<u64>>>) -> Self {
+        self.history_params = params.clone();
+        self
+    }
+
+    pub fn history_result(self, result: Vec<BalanceSnapshot>) -> Self {
+        self.history_results.lock().unwrap().push(result);
+        self
+    }
+}
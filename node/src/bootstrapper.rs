@@ -3,18 +3,21 @@ use crate::accountant::{DEFAULT_PAYABLE_SCAN_INTERVAL, DEFAULT_PAYMENT_RECEIVED_
 use crate::actor_system_factory::ActorFactoryReal;
 use crate::actor_system_factory::ActorSystemFactory;
 use crate::actor_system_factory::ActorSystemFactoryReal;
-use crate::blockchain::blockchain_interface::chain_id_from_name;
+use crate::blockchain::blockchain_interface::{chain_id_from_name, chain_name_from_id};
 use crate::crash_test_dummy::CrashTestDummy;
-use crate::database::db_initializer::{DbInitializer, DbInitializerReal};
+use crate::database::db_initializer::{DbInitializer, DbInitializerReal, CURRENT_SCHEMA_VERSION};
 use crate::db_config::config_dao::ConfigDaoReal;
 use crate::db_config::persistent_configuration::{
     PersistentConfiguration, PersistentConfigurationReal,
 };
 use crate::discriminator::DiscriminatorFactory;
-use crate::json_discriminator_factory::JsonDiscriminatorFactory;
+use crate::entry_dns::dns_socket_server::DNS_PORT;
+use crate::geo_db::GeoDbConfig;
 use crate::listener_handler::ListenerHandler;
 use crate::listener_handler::ListenerHandlerFactory;
 use crate::listener_handler::ListenerHandlerFactoryReal;
+use crate::masquerade_profile::MasqueradeDiscriminatorFactory;
+use crate::neighborhood::route_search::DEFAULT_ROUTE_SEARCH_THREADS;
 use crate::node_configurator::node_configurator_standard::{
     NodeConfiguratorStandardPrivileged, NodeConfiguratorStandardUnprivileged,
 };
@@ -24,31 +27,49 @@ use crate::server_initializer::LoggerInitializerWrapper;
 use crate::sub_lib::accountant;
 use crate::sub_lib::accountant::AccountantConfig;
 use crate::sub_lib::blockchain_bridge::BlockchainBridgeConfig;
-use crate::sub_lib::cryptde::CryptDE;
+use crate::sub_lib::clock_skew;
+use crate::sub_lib::clock_skew::{start_clock_skew_checker, DEFAULT_CLOCK_SKEW_TOLERANCE_SECS};
+use crate::sub_lib::cpu_affinity;
+use crate::sub_lib::cryptde::{CryptDE, PublicKey};
 use crate::sub_lib::cryptde_null::CryptDENull;
 use crate::sub_lib::cryptde_real::CryptDEReal;
+use crate::sub_lib::data_cap::DataCapConfig;
+use crate::sub_lib::descriptor_signature;
+use crate::sub_lib::deterministic;
+use crate::sub_lib::hybrid_kem::KemKeyPair;
+use crate::sub_lib::key_rotation::{load_or_rotate, KeyRotationConfig, KeyRotationOutcome};
+use crate::sub_lib::log_rotation::{start_log_rotation, LogRotationConfig};
 use crate::sub_lib::logger::Logger;
 use crate::sub_lib::neighborhood::NodeDescriptor;
 use crate::sub_lib::neighborhood::{NeighborhoodConfig, NeighborhoodMode};
 use crate::sub_lib::node_addr::NodeAddr;
+use crate::sub_lib::port_conflict::{conflict_message, PortChecker, PortCheckerReal};
+use crate::sub_lib::proxy_client::{DEFAULT_DNS_CACHE_SIZE, DEFAULT_DNS_LOOKUP_THREADS};
+use crate::sub_lib::proxy_server::{EncryptedSniFallback, MultipathMode, SocksConfig};
+use crate::sub_lib::redacted;
+use crate::sub_lib::redacted::Redacted;
 use crate::sub_lib::socket_server::SocketServer;
 use crate::sub_lib::ui_gateway::UiGatewayConfig;
-use crate::sub_lib::wallet::Wallet;
+use crate::sub_lib::wallet::{Wallet, WalletChange};
+use crate::updater::UpdateCheckConfig;
 use futures::try_ready;
 use itertools::Itertools;
 use log::LevelFilter;
 use masq_lib::command::StdStreams;
 use masq_lib::constants::{DEFAULT_CHAIN_NAME, DEFAULT_UI_PORT};
 use masq_lib::crash_point::CrashPoint;
-use masq_lib::shared_schema::ConfiguratorError;
+use masq_lib::exit_policy::ExitPolicy;
+use masq_lib::messages::UiEffectiveConfig;
+use masq_lib::secure_dns::SecureDnsServer;
+use masq_lib::shared_schema::{ConfiguratorError, ParamError};
 use std::collections::HashMap;
 use std::env::var;
 use std::fmt;
 use std::fmt::{Debug, Display, Error, Formatter};
 use std::net::SocketAddr;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 use std::vec::Vec;
 use tokio::prelude::stream::futures_unordered::FuturesUnordered;
 use tokio::prelude::Async;
@@ -287,7 +308,40 @@ impl Display for RealUser {
 pub struct BootstrapperConfig {
     // These fields can be set while privileged without penalty
     pub log_level: LevelFilter,
+    pub log_redaction: bool,
+    pub deterministic_seed_opt: Option<u64>,
+    pub cpu_affinity: Vec<usize>,
+    pub data_cap_opt: Option<DataCapConfig>,
+    pub port_reselection: bool,
+    pub sandbox: bool,
+    pub local_discovery: bool,
+    pub bandwidth_benchmark: bool,
+    pub hop_timing: bool,
+    pub gossip_recording: bool,
+    pub dump_neighborhood: bool,
+    // Keep the start of streams no protocol pack recognizes, for the diagnostics bundle
+    pub protocol_sniffing: bool,
+    // Sign and gossip what this Node has seen of its debtors' payments
+    pub publish_attestations: bool,
+    // Give wallets this Node has no payment record for the credit other Nodes attest they deserve
+    pub heed_attestations: bool,
+    // Short names of the masquerade profiles other Nodes should send this one's data in
+    pub masquerade_profiles: Vec<String>,
+    pub clock_skew_tolerance_secs: u32,
+    // NTP servers to check this computer's clock with; empty for no checks
+    pub time_servers: Vec<String>,
+    // The operator's signature of this Node's descriptor, made with the earning wallet's key
+    pub descriptor_signature_opt: Option<String>,
     pub dns_servers: Vec<SocketAddr>,
+    pub secure_dns_servers: Vec<SecureDnsServer>,
+    pub dns_cache_size: usize,
+    pub dns_lookup_threads: usize,
+    pub route_search_threads: usize,
+    pub exit_policy: ExitPolicy,
+    pub reputation_lists: Vec<String>,
+    pub stream_idle_timeout_opt: Option<Duration>,
+    pub multipath_mode: MultipathMode,
+    pub encrypted_sni_fallback: EncryptedSniFallback,
     pub accountant_config: AccountantConfig,
     pub crash_point: CrashPoint,
     pub clandestine_discriminator_factories: Vec<Box<dyn DiscriminatorFactory>>,
@@ -298,13 +352,32 @@ pub struct BootstrapperConfig {
     pub main_cryptde_null_opt: Option<CryptDENull>,
     pub alias_cryptde_null_opt: Option<CryptDENull>,
     pub real_user: RealUser,
+    pub socks_config_opt: Option<SocksConfig>,
+    pub dns_port: u16,
+    pub proxied_domains_opt: Option<Vec<String>>,
+    pub metrics_port_opt: Option<u16>,
+    pub update_check_opt: Option<UpdateCheckConfig>,
+    pub geo_db_opt: Option<GeoDbConfig>,
 
     // These fields must be set without privilege: otherwise the database will be created as root
     pub db_password_opt: Option<String>,
     pub clandestine_port_opt: Option<u16>,
+    pub additional_clandestine_ports: Vec<u16>,
     pub consuming_wallet: Option<Wallet>,
     pub earning_wallet: Wallet,
+    // The wallet this Node used to earn into, the block from which earning_wallet replaced it, and
+    // the old wallet's signature of the change
+    pub earning_wallet_change_opt: Option<WalletChange>,
+    // The old wallet's signature of a change asked for on this command line, until it's in the database
+    pub earning_wallet_change_signature_opt: Option<String>,
+    // The earning wallets that signed the descriptors in --neighbors, by Node key
+    pub neighbor_signers: HashMap<PublicKey, Wallet>,
     pub neighborhood_config: NeighborhoodConfig,
+    pub log_rotation: LogRotationConfig,
+    pub key_rotation_opt: Option<KeyRotationConfig>,
+    pub post_quantum: bool,
+    // Zero-hop, with no wallets, no payments and no scans for them
+    pub local_proxy: bool,
 }
 
 impl Default for BootstrapperConfig {
@@ -318,7 +391,34 @@ impl BootstrapperConfig {
         BootstrapperConfig {
             // These fields can be set while privileged without penalty
             log_level: LevelFilter::Off,
+            log_redaction: true,
+            deterministic_seed_opt: None,
+            cpu_affinity: vec![],
+            port_reselection: false,
+            sandbox: true,
+            local_discovery: false,
+            bandwidth_benchmark: false,
+            hop_timing: false,
+            gossip_recording: false,
+            dump_neighborhood: false,
+            protocol_sniffing: false,
+            publish_attestations: false,
+            heed_attestations: false,
+            masquerade_profiles: vec![],
+            clock_skew_tolerance_secs: DEFAULT_CLOCK_SKEW_TOLERANCE_SECS,
+            time_servers: vec![],
+            descriptor_signature_opt: None,
+            data_cap_opt: None,
             dns_servers: vec![],
+            secure_dns_servers: vec![],
+            dns_cache_size: DEFAULT_DNS_CACHE_SIZE,
+            dns_lookup_threads: DEFAULT_DNS_LOOKUP_THREADS,
+            route_search_threads: DEFAULT_ROUTE_SEARCH_THREADS,
+            exit_policy: ExitPolicy::default(),
+            reputation_lists: vec![],
+            stream_idle_timeout_opt: None,
+            multipath_mode: MultipathMode::Off,
+            encrypted_sni_fallback: EncryptedSniFallback::Refuse,
             accountant_config: AccountantConfig {
                 payable_scan_interval: Duration::from_secs(DEFAULT_PAYABLE_SCAN_INTERVAL),
                 payment_received_scan_interval: Duration::from_secs(
@@ -330,36 +430,92 @@ impl BootstrapperConfig {
             ui_gateway_config: UiGatewayConfig {
                 ui_port: DEFAULT_UI_PORT,
                 node_descriptor: String::from(""),
+                tls_opt: None,
+                auth_opt: None,
+                rest_port_opt: None,
+                grpc_port_opt: None,
+                health_port_opt: None,
+                data_directory: PathBuf::new(),
             },
             blockchain_bridge_config: BlockchainBridgeConfig {
                 blockchain_service_url: None,
                 chain_id: 3u8, /*DEFAULT_CHAIN_ID*/
+                faucet_url_opt: None,
                 gas_price: 1,
+                payment_token_opt: None,
+                price_feed_url_opt: None,
             },
             port_configurations: HashMap::new(),
             data_directory: PathBuf::new(),
             main_cryptde_null_opt: None,
             alias_cryptde_null_opt: None,
             real_user: RealUser::new(None, None, None),
+            socks_config_opt: None,
+            dns_port: DNS_PORT,
+            proxied_domains_opt: None,
+            metrics_port_opt: None,
+            update_check_opt: None,
+            geo_db_opt: None,
 
             // These fields must be set without privilege: otherwise the database will be created as root
             db_password_opt: None,
             clandestine_port_opt: None,
+            additional_clandestine_ports: vec![],
             earning_wallet: accountant::DEFAULT_EARNING_WALLET.clone(),
+            earning_wallet_change_opt: None,
+            earning_wallet_change_signature_opt: None,
+            neighbor_signers: HashMap::new(),
             consuming_wallet: None,
             neighborhood_config: NeighborhoodConfig {
                 mode: NeighborhoodMode::ZeroHop,
             },
+            log_rotation: LogRotationConfig::default(),
+            key_rotation_opt: None,
+            post_quantum: false,
+            local_proxy: false,
         }
     }
 
     pub fn merge_unprivileged(&mut self, unprivileged: BootstrapperConfig) {
         self.blockchain_bridge_config.gas_price = unprivileged.blockchain_bridge_config.gas_price;
         self.clandestine_port_opt = unprivileged.clandestine_port_opt;
+        self.additional_clandestine_ports = unprivileged.additional_clandestine_ports;
         self.neighborhood_config = unprivileged.neighborhood_config;
+        self.neighbor_signers = unprivileged.neighbor_signers;
         self.earning_wallet = unprivileged.earning_wallet;
+        self.earning_wallet_change_opt = unprivileged.earning_wallet_change_opt;
         self.consuming_wallet = unprivileged.consuming_wallet;
         self.db_password_opt = unprivileged.db_password_opt;
+        self.log_rotation = unprivileged.log_rotation;
+        self.key_rotation_opt = unprivileged.key_rotation_opt;
+        self.post_quantum = unprivileged.post_quantum;
+        self.local_proxy = unprivileged.local_proxy;
+    }
+
+    // An earning-only Node has an earning address of its own but no consuming wallet: it holds no
+    // key material, so it can route and exit for others but can't pay for traffic of its own.
+    pub fn is_earning_only(&self) -> bool {
+        self.consuming_wallet.is_none()
+            && self.earning_wallet != *accountant::DEFAULT_EARNING_WALLET
+    }
+
+    // The record the Node logs and broadcasts when it starts. A default earning wallet is no
+    // earning wallet of the operator's own.
+    pub fn effective_config(&self) -> UiEffectiveConfig {
+        UiEffectiveConfig {
+            chain: chain_name_from_id(self.blockchain_bridge_config.chain_id).to_string(),
+            chain_id: self.blockchain_bridge_config.chain_id,
+            clandestine_port_opt: self.clandestine_port_opt,
+            ui_port: self.ui_gateway_config.ui_port,
+            dns_port: self.dns_port,
+            socks_port_opt: self.socks_config_opt.as_ref().map(|socks| socks.port),
+            metrics_port_opt: self.metrics_port_opt,
+            neighborhood_mode: self.neighborhood_config.mode.to_string(),
+            has_consuming_wallet: self.consuming_wallet.is_some(),
+            has_earning_wallet: self.earning_wallet != *accountant::DEFAULT_EARNING_WALLET,
+            node_version: env!("CARGO_PKG_VERSION").to_string(),
+            schema_version: CURRENT_SCHEMA_VERSION.to_string(),
+        }
     }
 }
 
@@ -368,6 +524,7 @@ pub struct Bootstrapper {
     listener_handlers: FuturesUnordered<Box<dyn ListenerHandler<Item = (), Error = ()>>>,
     actor_system_factory: Box<dyn ActorSystemFactory>,
     logger_initializer: Box<dyn LoggerInitializerWrapper>,
+    port_checker: Box<dyn PortChecker>,
     config: BootstrapperConfig,
 }
 
@@ -398,6 +555,9 @@ impl SocketServer<BootstrapperConfig> for Bootstrapper {
                 Ok(config) => config,
                 Err(e) => return Err(e),
             };
+        redacted::set_redaction(self.config.log_redaction);
+        deterministic::set_deterministic_seed(self.config.deterministic_seed_opt);
+        clock_skew::set_tolerance_secs(self.config.clock_skew_tolerance_secs);
 
         self.logger_initializer.init(
             self.config.data_directory.clone(),
@@ -405,6 +565,8 @@ impl SocketServer<BootstrapperConfig> for Bootstrapper {
             self.config.log_level,
             None,
         );
+        self.pin_to_cpus();
+        self.check_privileged_ports()?;
         self.listener_handlers =
             FuturesUnordered::<Box<dyn ListenerHandler<Item = (), Error = ()>>>::new();
 
@@ -434,9 +596,16 @@ impl SocketServer<BootstrapperConfig> for Bootstrapper {
         let unprivileged_config = NodeConfiguratorStandardUnprivileged::new(&self.config)
             .configure(&args.to_vec(), streams)?;
         self.config.merge_unprivileged(unprivileged_config);
-        self.set_up_clandestine_port();
+        start_log_rotation(
+            self.config.data_directory.clone(),
+            &self.config.log_rotation,
+        );
+        start_clock_skew_checker(self.config.time_servers.clone());
+        self.set_up_clandestine_port()?;
+        let main_cryptde_real_opt = self.load_main_cryptde_real_opt();
         let (cryptde_ref, _) = Bootstrapper::initialize_cryptdes(
             &self.config.main_cryptde_null_opt,
+            main_cryptde_real_opt,
             &self.config.alias_cryptde_null_opt,
             self.config.blockchain_bridge_config.chain_id,
         );
@@ -446,6 +615,9 @@ impl SocketServer<BootstrapperConfig> for Bootstrapper {
             streams,
             self.config.blockchain_bridge_config.chain_id,
         );
+        self.report_signed_descriptor(streams);
+        self.report_earning_only();
+        self.report_effective_config();
         let stream_handler_pool_subs = self
             .actor_system_factory
             .make_and_start_actors(self.config.clone(), Box::new(ActorFactoryReal {}));
@@ -458,6 +630,115 @@ impl SocketServer<BootstrapperConfig> for Bootstrapper {
 }
 
 impl Bootstrapper {
+    fn report_earning_only(&self) {
+        if self.config.is_earning_only() {
+            info!(
+                Logger::new("Bootstrapper"),
+                "Earning-only: no consuming wallet, so this Node will route and exit for others, earning into {}, but can't consume",
+                Redacted(&self.config.earning_wallet)
+            );
+        }
+    }
+
+    // One line of JSON, for log scrapers as much as for people
+    fn report_effective_config(&self) {
+        info!(
+            Logger::new("Bootstrapper"),
+            "Effective configuration: {}",
+            serde_json::to_string(&self.config.effective_config()).expect("Serialization problem")
+        );
+    }
+
+    // A descriptor changes with the Node's IP address, and its old signature won't fit the new one
+    fn report_signed_descriptor(&self, streams: &mut StdStreams<'_>) {
+        let signature = match &self.config.descriptor_signature_opt {
+            Some(signature) => signature,
+            None => return,
+        };
+        let descriptor = &self.config.ui_gateway_config.node_descriptor;
+        let earning_wallet = &self.config.earning_wallet;
+        if descriptor_signature::is_signed_by(descriptor, earning_wallet, signature) {
+            let signed_msg = format!(
+                "MASQ Node signed descriptor: {}",
+                descriptor_signature::signed_descriptor(descriptor, earning_wallet, signature)
+            );
+            writeln!(streams.stdout, "{}", signed_msg).expect("Internal error");
+            info!(Logger::new("Bootstrapper"), "{}", signed_msg);
+        } else {
+            warning!(
+                Logger::new("Bootstrapper"),
+                "The --descriptor-signature isn't earning wallet {}'s signature of this Node's descriptor \
+                 {}; sign the descriptor again and publish the new signature",
+                earning_wallet,
+                descriptor
+            );
+        }
+    }
+
+    fn pin_to_cpus(&self) {
+        if self.config.cpu_affinity.is_empty() {
+            return;
+        }
+        let logger = Logger::new("Bootstrapper");
+        match cpu_affinity::pin_to_cpus(&self.config.cpu_affinity) {
+            Ok(()) => info!(
+                logger,
+                "Running on CPU cores {:?}", self.config.cpu_affinity
+            ),
+            Err(msg) => warning!(
+                logger,
+                "{}; running wherever the operating system puts the Node",
+                msg
+            ),
+        }
+    }
+
+    // The HTTP and TLS ports can't go anywhere else, so another program on one of them is fatal;
+    // the UI and SOCKS ports are the user's to change
+    fn check_privileged_ports(&self) -> Result<(), ConfiguratorError> {
+        let socks_port_opt = self
+            .config
+            .socks_config_opt
+            .as_ref()
+            .map(|socks_config| socks_config.port);
+        let mut param_errors = vec![];
+        let ui_port = self.config.ui_gateway_config.ui_port;
+        if let Some(holder) = self.port_checker.holder(ui_port) {
+            param_errors.push(ParamError::new(
+                "ui-port",
+                &conflict_message(ui_port, &holder),
+            ));
+        }
+        let ports = self
+            .config
+            .port_configurations
+            .keys()
+            .cloned()
+            .sorted()
+            .collect::<Vec<u16>>();
+        for port in ports {
+            if let Some(holder) = self.port_checker.holder(port) {
+                if Some(port) == socks_port_opt {
+                    param_errors.push(ParamError::new(
+                        "socks-port",
+                        &conflict_message(port, &holder),
+                    ));
+                } else {
+                    panic!(
+                        "Could not listen on port {}: {}",
+                        port,
+                        conflict_message(port, &holder)
+                    );
+                }
+            }
+        }
+        if param_errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfiguratorError::new(param_errors))
+        }
+    }
+
     pub fn new(logger_initializer: Box<dyn LoggerInitializerWrapper>) -> Bootstrapper {
         Bootstrapper {
             listener_handler_factory: Box::new(ListenerHandlerFactoryReal::new()),
@@ -465,6 +746,7 @@ impl Bootstrapper {
                 FuturesUnordered::<Box<dyn ListenerHandler<Item = (), Error = ()>>>::new(),
             actor_system_factory: Box::new(ActorSystemFactoryReal {}),
             logger_initializer,
+            port_checker: Box::new(PortCheckerReal::new()),
             config: BootstrapperConfig::new(),
         }
     }
@@ -476,6 +758,7 @@ impl Bootstrapper {
     ) -> (&'static dyn CryptDE, &'static dyn CryptDE) {
         Self::initialize_cryptdes(
             main_cryptde_null_opt,
+            None,
             alias_cryptde_null_opt,
             masq_lib::test_utils::utils::DEFAULT_CHAIN_ID,
         )
@@ -483,14 +766,20 @@ impl Bootstrapper {
 
     fn initialize_cryptdes(
         main_cryptde_null_opt: &Option<CryptDENull>,
+        main_cryptde_real_opt: Option<CryptDEReal>,
         alias_cryptde_null_opt: &Option<CryptDENull>,
         chain_id: u8,
     ) -> (&'static dyn CryptDE, &'static dyn CryptDE) {
-        match main_cryptde_null_opt {
-            Some(cryptde_null) => unsafe {
+        match (main_cryptde_null_opt, main_cryptde_real_opt) {
+            (Some(cryptde_null), _) => unsafe {
                 MAIN_CRYPTDE_BOX_OPT = Some(Box::new(cryptde_null.clone()))
             },
-            None => unsafe { MAIN_CRYPTDE_BOX_OPT = Some(Box::new(CryptDEReal::new(chain_id))) },
+            (None, Some(cryptde_real)) => unsafe {
+                MAIN_CRYPTDE_BOX_OPT = Some(Box::new(cryptde_real))
+            },
+            (None, None) => unsafe {
+                MAIN_CRYPTDE_BOX_OPT = Some(Box::new(CryptDEReal::new(chain_id)))
+            },
         }
         match alias_cryptde_null_opt {
             Some(cryptde_null) => unsafe {
@@ -501,6 +790,69 @@ impl Bootstrapper {
         (main_cryptde_ref(), alias_cryptde_ref())
     }
 
+    // Ordinarily the main key is made up fresh for every run; with key rotation, it's kept in the
+    // database until it gets too old. Either way, post-quantum sealing needs a Kyber key to go with it.
+    fn load_main_cryptde_real_opt(&self) -> Option<CryptDEReal> {
+        if self.config.main_cryptde_null_opt.is_some() {
+            return None;
+        }
+        let chain_id = self.config.blockchain_bridge_config.chain_id;
+        match &self.config.key_rotation_opt {
+            Some(key_rotation) => {
+                let db_password = self
+                    .config
+                    .db_password_opt
+                    .as_ref()
+                    .expect("Configurator allowed key rotation without a database password");
+                Some(Self::load_main_cryptde_real(
+                    &self.config.data_directory,
+                    db_password,
+                    key_rotation,
+                    self.config.post_quantum,
+                    chain_id,
+                ))
+            }
+            None if self.config.post_quantum => {
+                Some(CryptDEReal::new(chain_id).with_kem_key_pair(KemKeyPair::new()))
+            }
+            None => None,
+        }
+    }
+
+    fn load_main_cryptde_real(
+        data_directory: &Path,
+        db_password: &str,
+        key_rotation: &KeyRotationConfig,
+        post_quantum: bool,
+        chain_id: u8,
+    ) -> CryptDEReal {
+        let conn = DbInitializerReal::new()
+            .initialize(&data_directory.to_path_buf(), chain_id, true)
+            .expect("Cannot initialize database");
+        let mut persistent_config =
+            PersistentConfigurationReal::new(Box::new(ConfigDaoReal::new(conn)));
+        let (cryptde, outcome) = load_or_rotate(
+            &mut persistent_config,
+            db_password,
+            key_rotation,
+            post_quantum,
+            chain_id,
+            SystemTime::now(),
+        )
+        .unwrap_or_else(|pce| panic!("Could not load clandestine keys: {:?}", pce));
+        let logger = Logger::new("Bootstrapper");
+        match outcome {
+            KeyRotationOutcome::Created => info!(logger, "Created a new clandestine key"),
+            KeyRotationOutcome::Rotated => info!(
+                logger,
+                "Replaced the clandestine key; the previous one will keep working for {} more hours",
+                key_rotation.overlap.as_secs() / 3600
+            ),
+            KeyRotationOutcome::Retained => (),
+        }
+        cryptde
+    }
+
     fn report_local_descriptor(
         cryptde: &dyn CryptDE,
         node_addr_opt: Option<NodeAddr>,
@@ -528,7 +880,7 @@ impl Bootstrapper {
         descriptor
     }
 
-    fn set_up_clandestine_port(&mut self) {
+    fn set_up_clandestine_port(&mut self) -> Result<(), ConfiguratorError> {
         if let NeighborhoodMode::Standard(node_addr, neighbor_configs, rate_pack) =
             &self.config.neighborhood_config.mode
         {
@@ -542,20 +894,43 @@ impl Bootstrapper {
             let config_dao = ConfigDaoReal::new(conn);
             let mut persistent_config = PersistentConfigurationReal::new(Box::new(config_dao));
             let clandestine_port = self.establish_clandestine_port(&mut persistent_config);
-            let mut listener_handler = self.listener_handler_factory.make();
-            listener_handler
-                .bind_port_and_configuration(
-                    clandestine_port,
-                    PortConfiguration {
-                        discriminator_factories: vec![Box::new(JsonDiscriminatorFactory::new())],
-                        is_clandestine: true,
-                    },
-                )
-                .expect("Failed to bind ListenerHandler to clandestine port");
-            self.listener_handlers.push(listener_handler);
+            let clandestine_port =
+                self.check_clandestine_port(clandestine_port, &mut persistent_config)?;
+            let mut clandestine_ports = vec![clandestine_port];
+            self.config
+                .additional_clandestine_ports
+                .iter()
+                .for_each(|port| {
+                    if !clandestine_ports.contains(port) {
+                        clandestine_ports.push(*port)
+                    }
+                });
+            for port in &clandestine_ports[1..] {
+                if let Some(holder) = self.port_checker.holder(*port) {
+                    return Err(ConfiguratorError::required(
+                        "additional-clandestine-ports",
+                        &conflict_message(*port, &holder),
+                    ));
+                }
+            }
+            for port in &clandestine_ports {
+                let mut listener_handler = self.listener_handler_factory.make();
+                listener_handler
+                    .bind_port_and_configuration(
+                        *port,
+                        PortConfiguration {
+                            discriminator_factories: vec![Box::new(
+                                MasqueradeDiscriminatorFactory::new(),
+                            )],
+                            is_clandestine: true,
+                        },
+                    )
+                    .expect("Failed to bind ListenerHandler to clandestine port");
+                self.listener_handlers.push(listener_handler);
+            }
             self.config.neighborhood_config = NeighborhoodConfig {
                 mode: NeighborhoodMode::Standard(
-                    NodeAddr::new(&node_addr.ip_addr(), &[clandestine_port]),
+                    NodeAddr::new(&node_addr.ip_addr(), &clandestine_ports),
                     neighbor_configs.clone(),
                     rate_pack.clone(),
                 ),
@@ -563,7 +938,44 @@ impl Bootstrapper {
         }
         self.config
             .clandestine_discriminator_factories
-            .push(Box::new(JsonDiscriminatorFactory::new()));
+            .push(Box::new(MasqueradeDiscriminatorFactory::new()));
+        Ok(())
+    }
+
+    // With --port-reselection on, a clandestine port another program has taken is traded for a
+    // free one, which is kept in the database as if it had been specified
+    fn check_clandestine_port(
+        &self,
+        port: u16,
+        persistent_config: &mut dyn PersistentConfiguration,
+    ) -> Result<u16, ConfiguratorError> {
+        let holder = match self.port_checker.holder(port) {
+            None => return Ok(port),
+            Some(holder) => holder,
+        };
+        if !self.config.port_reselection {
+            return Err(ConfiguratorError::required(
+                "clandestine-port",
+                &format!(
+                    "{}; specify another, or use --port-reselection on",
+                    conflict_message(port, &holder)
+                ),
+            ));
+        }
+        let new_port = self.port_checker.free_clandestine_port();
+        if let Err(pce) = persistent_config.set_clandestine_port(new_port) {
+            panic!(
+                "Database is corrupt: error setting clandestine port: {:?}",
+                pce
+            )
+        }
+        warning!(
+            Logger::new("Bootstrapper"),
+            "{}; moved the clandestine port to {} for good",
+            conflict_message(port, &holder),
+            new_port
+        );
+        Ok(new_port)
     }
 
     fn establish_clandestine_port(
@@ -626,11 +1038,12 @@ mod tests {
     use crate::test_utils::tokio_wrapper_mocks::ReadHalfWrapperMock;
     use crate::test_utils::tokio_wrapper_mocks::WriteHalfWrapperMock;
     use crate::test_utils::{assert_contains, rate_pack, ArgsBuilder};
+    use crate::test_utils::{make_paying_wallet, make_wallet};
     use actix::Recipient;
     use actix::System;
     use lazy_static::lazy_static;
     use masq_lib::constants::DEFAULT_CHAIN_NAME;
-    use masq_lib::shared_schema::ParamError;
+    use masq_lib::shared_schema::{ParamError, ParamErrorCode};
     use masq_lib::test_utils::environment_guard::ClapGuard;
     use masq_lib::test_utils::fake_stream_holder::FakeStreamHolder;
     use masq_lib::test_utils::utils::{ensure_node_home_directory_exists, DEFAULT_CHAIN_ID};
@@ -1026,6 +1439,131 @@ mod tests {
         )
     }
 
+    #[test]
+    fn initialize_as_privileged_sets_log_redaction() {
+        let _lock = INITIALIZATION.lock();
+        let data_dir = ensure_node_home_directory_exists(
+            "bootstrapper",
+            "initialize_as_privileged_sets_log_redaction",
+        );
+        let make_subject = || {
+            let mut listener_handler_factory = ListenerHandlerFactoryMock::new();
+            listener_handler_factory.add(Box::new(
+                ListenerHandlerNull::new(vec![]).bind_port_result(Ok(())),
+            ));
+            listener_handler_factory.add(Box::new(
+                ListenerHandlerNull::new(vec![]).bind_port_result(Ok(())),
+            ));
+            let mut subject = Bootstrapper::new(Box::new(LoggerInitializerWrapperMock::new()));
+            subject.listener_handler_factory = Box::new(listener_handler_factory);
+            subject
+        };
+        let args_builder = || {
+            ArgsBuilder::new()
+                .param("--data-directory", data_dir.to_str().unwrap())
+                .param("--ip", "2.2.2.2")
+                .param("--real-user", "123:456:/home/booga")
+        };
+        let default_args: Vec<String> = args_builder().into();
+        let off_args: Vec<String> = args_builder().param("--log-redaction", "off").into();
+
+        make_subject()
+            .initialize_as_privileged(&default_args, &mut FakeStreamHolder::new().streams())
+            .unwrap();
+        let default_redaction = redacted::redaction_enabled();
+        make_subject()
+            .initialize_as_privileged(&off_args, &mut FakeStreamHolder::new().streams())
+            .unwrap();
+        let off_redaction = redacted::redaction_enabled();
+
+        assert_eq!(default_redaction, true);
+        assert_eq!(off_redaction, false);
+    }
+
+    #[test]
+    fn initialize_as_privileged_warns_and_carries_on_when_it_cannot_pin_to_cpus() {
+        init_test_logging();
+        let _lock = INITIALIZATION.lock();
+        let data_dir = ensure_node_home_directory_exists(
+            "bootstrapper",
+            "initialize_as_privileged_warns_and_carries_on_when_it_cannot_pin_to_cpus",
+        );
+        let mut listener_handler_factory = ListenerHandlerFactoryMock::new();
+        listener_handler_factory.add(Box::new(
+            ListenerHandlerNull::new(vec![]).bind_port_result(Ok(())),
+        ));
+        listener_handler_factory.add(Box::new(
+            ListenerHandlerNull::new(vec![]).bind_port_result(Ok(())),
+        ));
+        let mut subject = Bootstrapper::new(Box::new(LoggerInitializerWrapperMock::new()));
+        subject.listener_handler_factory = Box::new(listener_handler_factory);
+        let args: Vec<String> = ArgsBuilder::new()
+            .param("--data-directory", data_dir.to_str().unwrap())
+            .param("--ip", "2.2.2.2")
+            .param("--real-user", "123:456:/home/booga")
+            .param("--cpu-affinity", "1000000")
+            .into();
+
+        subject
+            .initialize_as_privileged(&args, &mut FakeStreamHolder::new().streams())
+            .unwrap();
+
+        assert_eq!(subject.config.cpu_affinity, vec![1000000]);
+        TestLogHandler::new()
+            .exists_log_containing("WARN: Bootstrapper: Can't pin to CPU core 1000000: ");
+    }
+
+    #[test]
+    fn initialize_as_privileged_reports_ui_port_another_program_has() {
+        let _lock = INITIALIZATION.lock();
+        let data_dir = ensure_node_home_directory_exists(
+            "bootstrapper",
+            "initialize_as_privileged_reports_ui_port_another_program_has",
+        );
+        let mut subject = Bootstrapper::new(Box::new(LoggerInitializerWrapperMock::new()));
+        subject.port_checker =
+            Box::new(PortCheckerMock::new().holder(5333, "MASQNode (process 1234)"));
+        let args: Vec<String> = ArgsBuilder::new()
+            .param("--data-directory", data_dir.to_str().unwrap())
+            .param("--ip", "2.2.2.2")
+            .param("--real-user", "123:456:/home/booga")
+            .param("--ui-port", "5333")
+            .into();
+
+        let result =
+            subject.initialize_as_privileged(&args, &mut FakeStreamHolder::new().streams());
+
+        assert_eq!(
+            result,
+            Err(ConfiguratorError::required(
+                "ui-port",
+                "Port 5333 is already in use by MASQNode (process 1234)"
+            ))
+        );
+        assert_eq!(subject.listener_handlers.len(), 0);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Could not listen on port 80: Port 80 is already in use by nginx (process 1234)"
+    )]
+    fn initialize_as_privileged_panics_if_another_program_has_the_http_port() {
+        let _lock = INITIALIZATION.lock();
+        let data_dir = ensure_node_home_directory_exists(
+            "bootstrapper",
+            "initialize_as_privileged_panics_if_another_program_has_the_http_port",
+        );
+        let mut subject = Bootstrapper::new(Box::new(LoggerInitializerWrapperMock::new()));
+        subject.port_checker = Box::new(PortCheckerMock::new().holder(80, "nginx (process 1234)"));
+        let args: Vec<String> = ArgsBuilder::new()
+            .param("--data-directory", data_dir.to_str().unwrap())
+            .param("--ip", "2.2.2.2")
+            .param("--real-user", "123:456:/home/booga")
+            .into();
+
+        let _ = subject.initialize_as_privileged(&args, &mut FakeStreamHolder::new().streams());
+    }
+
     #[test]
     fn initialize_as_privileged_handles_error_from_configurator() {
         let logger_initializer = LoggerInitializerWrapperMock::new();
@@ -1038,6 +1576,7 @@ mod tests {
 
         assert_eq! (result, Err(ConfiguratorError::new(vec![ParamError {
             parameter: "<unknown>".to_string(),
+            code: ParamErrorCode::Invalid,
             reason: "Unfamiliar message: error: Found argument '--booga' which wasn't expected, or isn't valid in this context
 
 USAGE:
@@ -1116,6 +1655,114 @@ For more information try --help".to_string()
         assert_eq!(config.blockchain_bridge_config.gas_price, 11);
     }
 
+    #[test]
+    fn only_a_node_with_an_earning_address_and_no_consuming_wallet_is_earning_only() {
+        let mut config = BootstrapperConfig::new();
+        let default_wallets = config.is_earning_only();
+        config.earning_wallet = make_wallet("earning");
+        let earning_only = config.is_earning_only();
+        config.consuming_wallet = Some(make_wallet("consuming"));
+        let both_wallets = config.is_earning_only();
+
+        assert_eq!(default_wallets, false);
+        assert_eq!(earning_only, true);
+        assert_eq!(both_wallets, false);
+    }
+
+    #[test]
+    fn earning_only_node_says_it_cant_consume() {
+        init_test_logging();
+        let mut config = BootstrapperConfig::new();
+        config.earning_wallet = make_wallet("earning");
+        let subject = BootstrapperBuilder::new().config(config).build();
+
+        subject.report_earning_only();
+
+        TestLogHandler::new().exists_log_containing(
+            "INFO: Bootstrapper: Earning-only: no consuming wallet, so this Node will route and exit for others, earning into ",
+        );
+    }
+
+    #[test]
+    fn effective_configuration_is_logged_as_one_line_of_json_without_wallet_addresses() {
+        init_test_logging();
+        let mut config = BootstrapperConfig::new();
+        config.blockchain_bridge_config.chain_id = 4;
+        config.clandestine_port_opt = Some(1234);
+        config.ui_gateway_config.ui_port = 5333;
+        config.dns_port = 5353;
+        config.socks_config_opt = Some(SocksConfig {
+            port: 1080,
+            credentials_opt: None,
+        });
+        config.neighborhood_config.mode = NeighborhoodMode::ConsumeOnly(vec![]);
+        config.consuming_wallet = Some(make_paying_wallet(b"effective consuming"));
+        let subject = BootstrapperBuilder::new().config(config).build();
+
+        let result = subject.config.effective_config();
+        subject.report_effective_config();
+
+        assert_eq!(
+            result,
+            UiEffectiveConfig {
+                chain: "rinkeby".to_string(),
+                chain_id: 4,
+                clandestine_port_opt: Some(1234),
+                ui_port: 5333,
+                dns_port: 5353,
+                socks_port_opt: Some(1080),
+                metrics_port_opt: None,
+                neighborhood_mode: "ConsumeOnly".to_string(),
+                has_consuming_wallet: true,
+                has_earning_wallet: false,
+                node_version: env!("CARGO_PKG_VERSION").to_string(),
+                schema_version: CURRENT_SCHEMA_VERSION.to_string(),
+            }
+        );
+        TestLogHandler::new().exists_log_containing(&format!(
+            "INFO: Bootstrapper: Effective configuration: {{\"chain\":\"rinkeby\",\"chainId\":4,\
+             \"clandestinePort\":1234,\"uiPort\":5333,\"dnsPort\":5353,\"socksPort\":1080,\
+             \"metricsPort\":null,\"neighborhoodMode\":\"ConsumeOnly\",\"hasConsumingWallet\":true,\
+             \"hasEarningWallet\":false,\"nodeVersion\":\"{}\",\"schemaVersion\":\"{}\"}}",
+            env!("CARGO_PKG_VERSION"),
+            CURRENT_SCHEMA_VERSION
+        ));
+    }
+
+    #[test]
+    fn a_descriptor_signature_is_checked_before_the_signed_descriptor_is_reported() {
+        init_test_logging();
+        let earning_wallet = make_paying_wallet(b"signing operator");
+        let descriptor = "AQIDBA@2.3.4.5:3456;4567";
+        let signed = descriptor_signature::sign_descriptor(descriptor, &earning_wallet).unwrap();
+        let signature = signed
+            .rsplit(descriptor_signature::SIGNATURE_DELIMITER)
+            .next()
+            .unwrap()
+            .to_string();
+        let make_subject = |node_descriptor: &str| {
+            let mut config = BootstrapperConfig::new();
+            config.earning_wallet = earning_wallet.as_address_wallet();
+            config.descriptor_signature_opt = Some(signature.clone());
+            config.ui_gateway_config.node_descriptor = node_descriptor.to_string();
+            BootstrapperBuilder::new().config(config).build()
+        };
+        let mut holder = FakeStreamHolder::new();
+
+        make_subject(descriptor).report_signed_descriptor(&mut holder.streams());
+        make_subject("AQIDBA@6.7.8.9:3456;4567").report_signed_descriptor(&mut holder.streams());
+
+        assert_eq!(
+            holder.stdout.get_string(),
+            format!("MASQ Node signed descriptor: {}\n", signed)
+        );
+        TestLogHandler::new().exists_log_containing(&format!(
+            "WARN: Bootstrapper: The --descriptor-signature isn't earning wallet {}'s signature of \
+             this Node's descriptor AQIDBA@6.7.8.9:3456;4567",
+            earning_wallet
+        ));
+    }
+
     #[test]
     fn initialize_with_clandestine_port_produces_expected_clandestine_discriminator_factories_vector(
     ) {
@@ -1237,7 +1884,8 @@ For more information try --help".to_string()
     #[test]
     fn initialize_cryptde_without_cryptde_null_uses_cryptde_real() {
         let _lock = INITIALIZATION.lock();
-        let (cryptde_init, _) = Bootstrapper::initialize_cryptdes(&None, &None, DEFAULT_CHAIN_ID);
+        let (cryptde_init, _) =
+            Bootstrapper::initialize_cryptdes(&None, None, &None, DEFAULT_CHAIN_ID);
 
         assert_eq!(main_cryptde_ref().public_key(), cryptde_init.public_key());
         // Brittle assertion: this may not be true forever
@@ -1252,12 +1900,96 @@ For more information try --help".to_string()
         let cryptde_null_public_key = cryptde_null.public_key().clone();
 
         let (cryptde, _) =
-            Bootstrapper::initialize_cryptdes(&Some(cryptde_null), &None, DEFAULT_CHAIN_ID);
+            Bootstrapper::initialize_cryptdes(&Some(cryptde_null), None, &None, DEFAULT_CHAIN_ID);
 
         assert_eq!(cryptde.public_key(), &cryptde_null_public_key);
         assert_eq!(main_cryptde_ref().public_key(), cryptde.public_key());
     }
 
+    #[test]
+    fn initialize_cryptde_with_cryptde_real_uses_cryptde_real() {
+        let _lock = INITIALIZATION.lock();
+        let cryptde_real = CryptDEReal::new(DEFAULT_CHAIN_ID);
+        let cryptde_real_public_key = cryptde_real.public_key().clone();
+
+        let (cryptde, _) =
+            Bootstrapper::initialize_cryptdes(&None, Some(cryptde_real), &None, DEFAULT_CHAIN_ID);
+
+        assert_eq!(cryptde.public_key(), &cryptde_real_public_key);
+        assert_eq!(main_cryptde_ref().public_key(), cryptde.public_key());
+    }
+
+    #[test]
+    fn load_main_cryptde_real_keeps_the_key_until_it_gets_too_old() {
+        init_test_logging();
+        let data_dir = ensure_node_home_directory_exists(
+            "bootstrapper",
+            "load_main_cryptde_real_keeps_the_key_until_it_gets_too_old",
+        );
+        let conn = DbInitializerReal::new()
+            .initialize(&data_dir, DEFAULT_CHAIN_ID, true)
+            .unwrap();
+        PersistentConfigurationReal::from(conn)
+            .change_password(None, "password")
+            .unwrap();
+        let young = KeyRotationConfig {
+            lifetime: Duration::from_secs(86400),
+            overlap: Duration::from_secs(3600),
+        };
+        let old = KeyRotationConfig {
+            lifetime: Duration::from_secs(0),
+            overlap: Duration::from_secs(3600),
+        };
+
+        let first = Bootstrapper::load_main_cryptde_real(
+            &data_dir,
+            "password",
+            &young,
+            false,
+            DEFAULT_CHAIN_ID,
+        );
+        let second = Bootstrapper::load_main_cryptde_real(
+            &data_dir,
+            "password",
+            &young,
+            false,
+            DEFAULT_CHAIN_ID,
+        );
+        let third = Bootstrapper::load_main_cryptde_real(
+            &data_dir,
+            "password",
+            &old,
+            false,
+            DEFAULT_CHAIN_ID,
+        );
+
+        assert_eq!(second.public_key(), first.public_key());
+        assert_ne!(third.public_key(), first.public_key());
+        assert_eq!(
+            third.key_succession_opt().unwrap().previous_public_key,
+            first.public_key().clone()
+        );
+        let tlh = TestLogHandler::new();
+        tlh.exists_log_containing("INFO: Bootstrapper: Created a new clandestine key");
+        tlh.exists_log_containing("INFO: Bootstrapper: Replaced the clandestine key; the previous one will keep working for 1 more hours");
+    }
+
+    #[test]
+    fn load_main_cryptde_real_opt_gives_a_kyber_key_for_post_quantum_sealing() {
+        let mut config = BootstrapperConfig::new();
+        config.post_quantum = true;
+        let subject = BootstrapperBuilder::new().config(config).build();
+
+        let result = subject.load_main_cryptde_real_opt().unwrap();
+
+        assert!(result.kem_key_pair_opt().is_some());
+        assert!(BootstrapperBuilder::new()
+            .config(BootstrapperConfig::new())
+            .build()
+            .load_main_cryptde_real_opt()
+            .is_none());
+    }
+
     #[test]
     fn initialize_cryptde_and_report_local_descriptor_with_ip_address() {
         let _lock = INITIALIZATION.lock();
@@ -1271,7 +2003,7 @@ For more information try --help".to_string()
             let mut streams = holder.streams();
 
             let (cryptde_ref, _) =
-                Bootstrapper::initialize_cryptdes(&None, &None, DEFAULT_CHAIN_ID);
+                Bootstrapper::initialize_cryptdes(&None, None, &None, DEFAULT_CHAIN_ID);
             Bootstrapper::report_local_descriptor(
                 cryptde_ref,
                 Some(node_addr),
@@ -1328,7 +2060,7 @@ For more information try --help".to_string()
             let mut streams = holder.streams();
 
             let (main_cryptde_ref, alias_cryptde_ref) =
-                Bootstrapper::initialize_cryptdes(&None, &None, DEFAULT_CHAIN_ID);
+                Bootstrapper::initialize_cryptdes(&None, None, &None, DEFAULT_CHAIN_ID);
             Bootstrapper::report_local_descriptor(
                 main_cryptde_ref,
                 None,
@@ -1603,7 +2335,7 @@ For more information try --help".to_string()
             .config(config)
             .build();
 
-        subject.set_up_clandestine_port();
+        subject.set_up_clandestine_port().unwrap();
 
         let conn = DbInitializerReal::new()
             .initialize(&data_dir, chain_id, true)
@@ -1645,6 +2377,199 @@ For more information try --help".to_string()
         assert_eq!(0, clandestine_discriminators.len()); // Used to be 1, now 0 after removal
     }
 
+    #[test]
+    fn set_up_clandestine_port_binds_and_advertises_additional_clandestine_ports() {
+        let data_dir = ensure_node_home_directory_exists(
+            "bootstrapper",
+            "set_up_clandestine_port_binds_and_advertises_additional_clandestine_ports",
+        );
+        let mut config = BootstrapperConfig::new();
+        config.neighborhood_config = NeighborhoodConfig {
+            mode: NeighborhoodMode::Standard(
+                NodeAddr::new(&IpAddr::from_str("1.2.3.4").unwrap(), &[]),
+                vec![],
+                rate_pack(100),
+            ),
+        };
+        config.data_directory = data_dir;
+        config.clandestine_port_opt = Some(1234);
+        config.additional_clandestine_ports = vec![8443, 1234, 8444];
+        let listener_handlers = (0..3)
+            .map(|_| ListenerHandlerNull::new(vec![]).bind_port_result(Ok(())))
+            .collect::<Vec<ListenerHandlerNull>>();
+        let logs = listener_handlers
+            .iter()
+            .map(|handler| handler.get_test_log())
+            .collect::<Vec<Arc<Mutex<TestLog>>>>();
+        let subject = listener_handlers
+            .into_iter()
+            .fold(BootstrapperBuilder::new(), |builder, handler| {
+                builder.add_listener_handler(Box::new(handler))
+            });
+        let mut subject = subject.config(config).build();
+
+        subject.set_up_clandestine_port().unwrap();
+
+        assert_eq!(
+            subject
+                .config
+                .neighborhood_config
+                .mode
+                .node_addr_opt()
+                .unwrap()
+                .ports(),
+            vec![1234u16, 8443, 8444],
+        );
+        assert_eq!(3, subject.listener_handlers.len());
+        let mut bound_ports = logs
+            .iter()
+            .flat_map(|log| log.lock().unwrap().dump())
+            .collect::<Vec<String>>();
+        bound_ports.sort();
+        assert_eq!(
+            bound_ports,
+            vec![
+                "bind_port_and_configuration (1234, PortConfiguration {is_clandestine: true, ...})"
+                    .to_string(),
+                "bind_port_and_configuration (8443, PortConfiguration {is_clandestine: true, ...})"
+                    .to_string(),
+                "bind_port_and_configuration (8444, PortConfiguration {is_clandestine: true, ...})"
+                    .to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn set_up_clandestine_port_refuses_a_port_another_program_has() {
+        let data_dir = ensure_node_home_directory_exists(
+            "bootstrapper",
+            "set_up_clandestine_port_refuses_a_port_another_program_has",
+        );
+        let mut config = BootstrapperConfig::new();
+        config.neighborhood_config = NeighborhoodConfig {
+            mode: NeighborhoodMode::Standard(
+                NodeAddr::new(&IpAddr::from_str("1.2.3.4").unwrap(), &[]),
+                vec![],
+                rate_pack(100),
+            ),
+        };
+        config.data_directory = data_dir;
+        config.clandestine_port_opt = Some(1234);
+        let mut subject = BootstrapperBuilder::new()
+            .port_checker(PortCheckerMock::new().holder(1234, "nginx (process 4321)"))
+            .config(config)
+            .build();
+
+        let result = subject.set_up_clandestine_port();
+
+        assert_eq!(
+            result,
+            Err(ConfiguratorError::required(
+                "clandestine-port",
+                "Port 1234 is already in use by nginx (process 4321); specify another, or use --port-reselection on"
+            ))
+        );
+        assert_eq!(0, subject.listener_handlers.len());
+    }
+
+    #[test]
+    fn set_up_clandestine_port_moves_a_port_another_program_has_when_asked_to() {
+        init_test_logging();
+        let data_dir = ensure_node_home_directory_exists(
+            "bootstrapper",
+            "set_up_clandestine_port_moves_a_port_another_program_has_when_asked_to",
+        );
+        let mut config = BootstrapperConfig::new();
+        config.neighborhood_config = NeighborhoodConfig {
+            mode: NeighborhoodMode::Standard(
+                NodeAddr::new(&IpAddr::from_str("1.2.3.4").unwrap(), &[]),
+                vec![],
+                rate_pack(100),
+            ),
+        };
+        config.data_directory = data_dir.clone();
+        config.clandestine_port_opt = Some(1234);
+        config.port_reselection = true;
+        let chain_id = config.blockchain_bridge_config.chain_id;
+        let listener_handler = ListenerHandlerNull::new(vec![]).bind_port_result(Ok(()));
+        let log = listener_handler.get_test_log();
+        let mut subject = BootstrapperBuilder::new()
+            .add_listener_handler(Box::new(listener_handler))
+            .port_checker(
+                PortCheckerMock::new()
+                    .holder(1234, "nginx (process 4321)")
+                    .free_clandestine_port_result(2345),
+            )
+            .config(config)
+            .build();
+
+        subject.set_up_clandestine_port().unwrap();
+
+        let conn = DbInitializerReal::new()
+            .initialize(&data_dir, chain_id, true)
+            .unwrap();
+        let config_dao = ConfigDaoReal::new(conn);
+        let persistent_config = PersistentConfigurationReal::new(Box::new(config_dao));
+        assert_eq!(
+            2345u16,
+            persistent_config.clandestine_port().unwrap().unwrap()
+        );
+        assert_eq!(
+            subject
+                .config
+                .neighborhood_config
+                .mode
+                .node_addr_opt()
+                .unwrap()
+                .ports(),
+            vec![2345u16],
+        );
+        assert_eq!(
+            log.lock().unwrap().dump(),
+            vec![
+                "bind_port_and_configuration (2345, PortConfiguration {is_clandestine: true, ...})"
+                    .to_string()
+            ]
+        );
+        TestLogHandler::new().exists_log_containing(
+            "WARN: Bootstrapper: Port 1234 is already in use by nginx (process 4321); moved the clandestine port to 2345 for good",
+        );
+    }
+
+    #[test]
+    fn set_up_clandestine_port_refuses_an_additional_port_another_program_has() {
+        let data_dir = ensure_node_home_directory_exists(
+            "bootstrapper",
+            "set_up_clandestine_port_refuses_an_additional_port_another_program_has",
+        );
+        let mut config = BootstrapperConfig::new();
+        config.neighborhood_config = NeighborhoodConfig {
+            mode: NeighborhoodMode::Standard(
+                NodeAddr::new(&IpAddr::from_str("1.2.3.4").unwrap(), &[]),
+                vec![],
+                rate_pack(100),
+            ),
+        };
+        config.data_directory = data_dir;
+        config.clandestine_port_opt = Some(1234);
+        config.additional_clandestine_ports = vec![8443];
+        config.port_reselection = true;
+        let mut subject = BootstrapperBuilder::new()
+            .port_checker(PortCheckerMock::new().holder(8443, "another program"))
+            .config(config)
+            .build();
+
+        let result = subject.set_up_clandestine_port();
+
+        assert_eq!(
+            result,
+            Err(ConfiguratorError::required(
+                "additional-clandestine-ports",
+                "Port 8443 is already in use by another program"
+            ))
+        );
+    }
+
     #[test]
     fn set_up_clandestine_port_handles_unspecified_port_in_standard_mode() {
         let cryptde_actual = CryptDENull::from(&PublicKey::new(&[1, 2, 3, 4]), DEFAULT_CHAIN_ID);
@@ -1675,7 +2600,7 @@ For more information try --help".to_string()
             .config(config)
             .build();
 
-        subject.set_up_clandestine_port();
+        subject.set_up_clandestine_port().unwrap();
 
         let conn = DbInitializerReal::new()
             .initialize(&data_dir, chain_id, true)
@@ -1723,7 +2648,7 @@ For more information try --help".to_string()
             .config(config)
             .build();
 
-        subject.set_up_clandestine_port();
+        subject.set_up_clandestine_port().unwrap();
 
         assert!(subject
             .config
@@ -1758,7 +2683,7 @@ For more information try --help".to_string()
             .config(config)
             .build();
 
-        subject.set_up_clandestine_port();
+        subject.set_up_clandestine_port().unwrap();
 
         assert!(subject
             .config
@@ -1786,7 +2711,7 @@ For more information try --help".to_string()
             .config(config)
             .build();
 
-        subject.set_up_clandestine_port();
+        subject.set_up_clandestine_port().unwrap();
 
         assert!(subject
             .config
@@ -1960,10 +2885,45 @@ For more information try --help".to_string()
         }
     }
 
+    struct PortCheckerMock {
+        holders: HashMap<u16, String>,
+        free_clandestine_port_results: RefCell<Vec<u16>>,
+    }
+
+    impl PortChecker for PortCheckerMock {
+        fn holder(&self, port: u16) -> Option<String> {
+            self.holders.get(&port).cloned()
+        }
+
+        fn free_clandestine_port(&self) -> u16 {
+            self.free_clandestine_port_results.borrow_mut().remove(0)
+        }
+    }
+
+    impl PortCheckerMock {
+        fn new() -> Self {
+            PortCheckerMock {
+                holders: HashMap::new(),
+                free_clandestine_port_results: RefCell::new(vec![]),
+            }
+        }
+
+        fn holder(mut self, port: u16, holder: &str) -> Self {
+            self.holders.insert(port, holder.to_string());
+            self
+        }
+
+        fn free_clandestine_port_result(self, result: u16) -> Self {
+            self.free_clandestine_port_results.borrow_mut().push(result);
+            self
+        }
+    }
+
     struct BootstrapperBuilder {
         actor_system_factory: Box<dyn ActorSystemFactory>,
         log_initializer_wrapper: Box<dyn LoggerInitializerWrapper>,
         listener_handler_factory: ListenerHandlerFactoryMock,
+        port_checker: PortCheckerMock,
         config: BootstrapperConfig,
     }
 
@@ -1974,6 +2934,7 @@ For more information try --help".to_string()
                 log_initializer_wrapper: Box::new(LoggerInitializerWrapperMock::new()),
                 // Don't modify this line unless you've already looked at DispatcherBuilder::add_listener_handler().
                 listener_handler_factory: ListenerHandlerFactoryMock::new(),
+                port_checker: PortCheckerMock::new(),
                 config: BootstrapperConfig::new(),
             }
         }
@@ -1994,6 +2955,11 @@ For more information try --help".to_string()
             self
         }
 
+        fn port_checker(mut self, port_checker: PortCheckerMock) -> Self {
+            self.port_checker = port_checker;
+            self
+        }
+
         fn config(mut self, config: BootstrapperConfig) -> Self {
             self.config = config;
             self
@@ -2007,6 +2973,7 @@ For more information try --help".to_string()
                     Box<dyn ListenerHandler<Item = (), Error = ()>>,
                 >::new(),
                 logger_initializer: self.log_initializer_wrapper,
+                port_checker: Box::new(self.port_checker),
                 config: self.config,
             }
         }
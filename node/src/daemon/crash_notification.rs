@@ -1,8 +1,16 @@
 // Copyright (c) 2019-2020, MASQ (https://masq.ai). All rights reserved.
 
 use actix::Message;
+use itertools::Itertools;
 use lazy_static::lazy_static;
+use masq_lib::constants::CURRENT_LOGFILE_NAME;
 use masq_lib::messages::CrashReason;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+// Enough to hold the last several lines of even a chatty log
+const LOG_TAIL_SIZE: u64 = 65536;
 
 lazy_static! {
     static ref RECOGNIZERS: Vec<Box<dyn Recognizer>> = vec![
@@ -36,6 +44,32 @@ impl CrashNotification {
     }
 }
 
+// The end of the log the Node was writing when it died, or nothing if it can't be read
+pub fn last_log_lines(data_directory: &Path, count: usize) -> Vec<String> {
+    let mut file = match File::open(data_directory.join(CURRENT_LOGFILE_NAME)) {
+        Ok(file) => file,
+        Err(_) => return vec![],
+    };
+    let start = match file.metadata() {
+        Ok(metadata) => metadata.len().saturating_sub(LOG_TAIL_SIZE),
+        Err(_) => return vec![],
+    };
+    let mut tail = vec![];
+    if file.seek(SeekFrom::Start(start)).is_err() || file.read_to_end(&mut tail).is_err() {
+        return vec![];
+    }
+    let text = String::from_utf8_lossy(&tail);
+    // If we started in the middle of the file, the first line is probably only part of one
+    let lines = text
+        .lines()
+        .skip(if start > 0 { 1 } else { 0 })
+        .collect_vec();
+    lines[lines.len().saturating_sub(count)..]
+        .iter()
+        .map(|line| line.to_string())
+        .collect()
+}
+
 struct ChildWaitFailureRecognizer {}
 
 const CHILD_WAIT_FAILURE_PREFIX: &str = "Child wait failure: ";
@@ -93,6 +127,8 @@ impl Recognizer for UnrecognizedRecognizer {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use masq_lib::test_utils::utils::ensure_node_home_directory_exists;
+    use std::io::Write;
 
     #[test]
     fn recognizes_wait_failure() {
@@ -147,4 +183,52 @@ mod tests {
             CrashReason::Unrecognized("unrecognizable".to_string())
         )
     }
+
+    #[test]
+    fn last_log_lines_come_from_the_end_of_the_current_log() {
+        let data_dir = ensure_node_home_directory_exists(
+            "crash_notification",
+            "last_log_lines_come_from_the_end_of_the_current_log",
+        );
+        let mut file = File::create(data_dir.join(CURRENT_LOGFILE_NAME)).unwrap();
+        (1..=5).for_each(|n| writeln!(file, "Line {}", n).unwrap());
+
+        let result = last_log_lines(&data_dir, 3);
+
+        assert_eq!(
+            result,
+            vec![
+                "Line 3".to_string(),
+                "Line 4".to_string(),
+                "Line 5".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn last_log_lines_skips_the_partial_line_at_the_start_of_a_long_log() {
+        let data_dir = ensure_node_home_directory_exists(
+            "crash_notification",
+            "last_log_lines_skips_the_partial_line_at_the_start_of_a_long_log",
+        );
+        let mut file = File::create(data_dir.join(CURRENT_LOGFILE_NAME)).unwrap();
+        writeln!(file, "{}", "x".repeat(LOG_TAIL_SIZE as usize)).unwrap();
+        writeln!(file, "Last line").unwrap();
+
+        let result = last_log_lines(&data_dir, 10);
+
+        assert_eq!(result, vec!["Last line".to_string()]);
+    }
+
+    #[test]
+    fn last_log_lines_is_empty_without_a_log() {
+        let data_dir = ensure_node_home_directory_exists(
+            "crash_notification",
+            "last_log_lines_is_empty_without_a_log",
+        );
+
+        let result = last_log_lines(&data_dir, 10);
+
+        assert_eq!(result, Vec::<String>::new());
+    }
 }
@@ -2,6 +2,7 @@
 
 use crate::bootstrapper::RealUser;
 use crate::daemon::launcher::LauncherReal;
+use crate::daemon::restart_policy::RestartPolicy;
 use crate::daemon::{
     ChannelFactory, ChannelFactoryReal, Daemon, DaemonBindMessage, Launcher, Recipients,
 };
@@ -17,22 +18,29 @@ use flexi_logger::LevelFilter;
 use itertools::Itertools;
 use masq_lib::command::{Command, StdStreams};
 use std::collections::HashMap;
+use std::path::PathBuf;
 
 pub trait RecipientsFactory {
-    fn make(&self, launcher: Box<dyn Launcher>, ui_port: u16) -> Recipients;
+    fn make(
+        &self,
+        launcher: Box<dyn Launcher>,
+        ui_gateway_config: UiGatewayConfig,
+        restart_policy: RestartPolicy,
+    ) -> Recipients;
 }
 
 #[derive(Default)]
 pub struct RecipientsFactoryReal {}
 
 impl RecipientsFactory for RecipientsFactoryReal {
-    fn make(&self, launcher: Box<dyn Launcher>, ui_port: u16) -> Recipients {
-        let ui_gateway_addr = UiGateway::new(&UiGatewayConfig {
-            ui_port,
-            node_descriptor: "".to_string(), // irrelevant; field should be removed
-        })
-        .start();
-        let daemon_addr = Daemon::new(launcher).start();
+    fn make(
+        &self,
+        launcher: Box<dyn Launcher>,
+        ui_gateway_config: UiGatewayConfig,
+        restart_policy: RestartPolicy,
+    ) -> Recipients {
+        let ui_gateway_addr = UiGateway::new(&ui_gateway_config).start();
+        let daemon_addr = Daemon::new(launcher, restart_policy).start();
         Recipients {
             ui_gateway_from_sub: ui_gateway_addr.clone().recipient(),
             ui_gateway_to_sub: ui_gateway_addr.clone().recipient(),
@@ -134,9 +142,20 @@ impl DaemonInitializer {
 
     fn bind(&mut self, sender: Sender<HashMap<String, String>>) -> u8 {
         let launcher = LauncherReal::new(sender);
-        let recipients = self
-            .recipients_factory
-            .make(Box::new(launcher), self.config.ui_port);
+        let recipients = self.recipients_factory.make(
+            Box::new(launcher),
+            UiGatewayConfig {
+                ui_port: self.config.ui_port,
+                node_descriptor: "".to_string(), // irrelevant; field should be removed
+                tls_opt: self.config.ui_tls_opt.clone(),
+                auth_opt: self.config.ui_auth_opt.clone(),
+                rest_port_opt: None,
+                grpc_port_opt: self.config.ui_grpc_port_opt,
+                health_port_opt: None,
+                data_directory: PathBuf::new(),
+            },
+            self.config.restart_policy,
+        );
         let bind_message = DaemonBindMessage {
             to_ui_message_recipient: recipients.ui_gateway_to_sub,
             from_ui_message_recipient: recipients.ui_gateway_from_sub,
@@ -179,15 +198,24 @@ mod tests {
     use std::iter::FromIterator;
     use std::net::{SocketAddr, TcpListener};
     use std::sync::{Arc, Mutex};
+    use std::time::Duration;
 
     struct RecipientsFactoryMock {
-        make_params: Arc<Mutex<Vec<(Box<dyn Launcher>, u16)>>>,
+        make_params: Arc<Mutex<Vec<(Box<dyn Launcher>, UiGatewayConfig, RestartPolicy)>>>,
         make_results: RefCell<Vec<Recipients>>,
     }
 
     impl RecipientsFactory for RecipientsFactoryMock {
-        fn make(&self, launcher: Box<dyn Launcher>, ui_port: u16) -> Recipients {
-            self.make_params.lock().unwrap().push((launcher, ui_port));
+        fn make(
+            &self,
+            launcher: Box<dyn Launcher>,
+            ui_gateway_config: UiGatewayConfig,
+            restart_policy: RestartPolicy,
+        ) -> Recipients {
+            self.make_params
+                .lock()
+                .unwrap()
+                .push((launcher, ui_gateway_config, restart_policy));
             self.make_results.borrow_mut().remove(0)
         }
     }
@@ -200,6 +228,15 @@ mod tests {
             }
         }
 
+        #[allow(clippy::type_complexity)]
+        fn make_params(
+            mut self,
+            params: &Arc<Mutex<Vec<(Box<dyn Launcher>, UiGatewayConfig, RestartPolicy)>>>,
+        ) -> Self {
+            self.make_params = params.clone();
+            self
+        }
+
         fn make_result(self, result: Recipients) -> Self {
             self.make_results.borrow_mut().push(result);
             self
@@ -281,9 +318,22 @@ mod tests {
             .data_dir_result(Some(data_dir));
         let logger_initializer_wrapper = LoggerInitializerWrapperMock::new();
         let port = find_free_port();
-        let config = InitializationConfig { ui_port: port };
+        let restart_policy = RestartPolicy {
+            max_retries: 3,
+            backoff: Duration::from_secs(7),
+        };
+        let config = InitializationConfig {
+            ui_port: port,
+            ui_tls_opt: None,
+            ui_auth_opt: None,
+            ui_grpc_port_opt: Some(5337),
+            restart_policy,
+        };
         let channel_factory = ChannelFactoryMock::new();
-        let addr_factory = RecipientsFactoryMock::new().make_result(recipients);
+        let make_params_arc = Arc::new(Mutex::new(vec![]));
+        let addr_factory = RecipientsFactoryMock::new()
+            .make_params(&make_params_arc)
+            .make_result(recipients);
         let rerunner = RerunnerMock::new();
         let mut subject = DaemonInitializer::new(
             &dirs_wrapper,
@@ -304,6 +354,10 @@ mod tests {
         let _ = daemon_recording.get_record::<DaemonBindMessage>(0);
         assert_eq!(ui_gateway_recording.len(), 1);
         assert_eq!(daemon_recording.len(), 1);
+        let make_params = make_params_arc.lock().unwrap();
+        assert_eq!(make_params[0].1.ui_port, port);
+        assert_eq!(make_params[0].1.grpc_port_opt, Some(5337));
+        assert_eq!(make_params[0].2, restart_policy);
     }
 
     #[test]
@@ -319,7 +373,13 @@ mod tests {
             .data_dir_result(Some(data_dir));
         let logger_initializer_wrapper = LoggerInitializerWrapperMock::new();
         let port = find_free_port();
-        let config = InitializationConfig { ui_port: port };
+        let config = InitializationConfig {
+            ui_port: port,
+            ui_tls_opt: None,
+            ui_auth_opt: None,
+            ui_grpc_port_opt: None,
+            restart_policy: RestartPolicy::default(),
+        };
         let (sender, receiver) = unbounded();
         let channel_factory = ChannelFactoryMock::new();
         let addr_factory = RecipientsFactoryMock::new();
@@ -370,7 +430,13 @@ mod tests {
         let mut subject = DaemonInitializer::new(
             &dirs_wrapper,
             Box::new(logger_initializer_wrapper),
-            InitializationConfig { ui_port: port },
+            InitializationConfig {
+                ui_port: port,
+                ui_tls_opt: None,
+                ui_auth_opt: None,
+                ui_grpc_port_opt: None,
+                restart_policy: RestartPolicy::default(),
+            },
             Box::new(ChannelFactoryMock::new()),
             Box::new(RecipientsFactoryMock::new()),
             Box::new(RerunnerMock::new()),
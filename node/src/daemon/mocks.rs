@@ -1,7 +1,10 @@
 // Copyright (c) 2019-2020, MASQ (https://masq.ai). All rights reserved.
 
 use crate::daemon::launch_verifier::{LaunchVerification, LaunchVerifier, VerifierTools};
+use dns_utility_lib::dns_modifier::DnsModifier;
+use dns_utility_lib::dns_modifier_factory::DnsModifierFactory;
 use std::cell::RefCell;
+use std::io;
 use std::sync::{Arc, Mutex};
 
 pub struct LaunchVerifierMock {
@@ -119,3 +122,104 @@ impl VerifierToolsMock {
         self
     }
 }
+
+pub struct DnsModifierFactoryMock {
+    make_results: RefCell<Vec<Option<Box<dyn DnsModifier>>>>,
+}
+
+impl DnsModifierFactory for DnsModifierFactoryMock {
+    fn make(&self) -> Option<Box<dyn DnsModifier>> {
+        self.make_results.borrow_mut().remove(0)
+    }
+}
+
+impl DnsModifierFactoryMock {
+    pub fn new() -> Self {
+        DnsModifierFactoryMock {
+            make_results: RefCell::new(vec![]),
+        }
+    }
+
+    pub fn make_result(self, result: Option<Box<dyn DnsModifier>>) -> Self {
+        self.make_results.borrow_mut().push(result);
+        self
+    }
+}
+
+pub struct DnsModifierMock {
+    subvert_params: Arc<Mutex<Vec<()>>>,
+    subvert_results: RefCell<Vec<Result<(), String>>>,
+    revert_params: Arc<Mutex<Vec<()>>>,
+    revert_results: RefCell<Vec<Result<(), String>>>,
+    inspect_results: RefCell<Vec<Result<String, String>>>,
+    manager_results: RefCell<Vec<Option<String>>>,
+}
+
+impl DnsModifier for DnsModifierMock {
+    fn type_name(&self) -> &'static str {
+        "DnsModifierMock"
+    }
+
+    fn subvert(&self) -> Result<(), String> {
+        self.subvert_params.lock().unwrap().push(());
+        self.subvert_results.borrow_mut().remove(0)
+    }
+
+    fn revert(&self) -> Result<(), String> {
+        self.revert_params.lock().unwrap().push(());
+        self.revert_results.borrow_mut().remove(0)
+    }
+
+    fn inspect(&self, stdout: &mut (dyn io::Write + Send)) -> Result<(), String> {
+        let dns_server_list = self.inspect_results.borrow_mut().remove(0)?;
+        write!(stdout, "{}", dns_server_list).unwrap();
+        Ok(())
+    }
+
+    fn manager(&self) -> Option<String> {
+        self.manager_results.borrow_mut().remove(0)
+    }
+}
+
+impl DnsModifierMock {
+    pub fn new() -> Self {
+        DnsModifierMock {
+            subvert_params: Arc::new(Mutex::new(vec![])),
+            subvert_results: RefCell::new(vec![]),
+            revert_params: Arc::new(Mutex::new(vec![])),
+            revert_results: RefCell::new(vec![]),
+            inspect_results: RefCell::new(vec![]),
+            manager_results: RefCell::new(vec![]),
+        }
+    }
+
+    pub fn subvert_params(mut self, params: &Arc<Mutex<Vec<()>>>) -> Self {
+        self.subvert_params = params.clone();
+        self
+    }
+
+    pub fn subvert_result(self, result: Result<(), String>) -> Self {
+        self.subvert_results.borrow_mut().push(result);
+        self
+    }
+
+    pub fn revert_params(mut self, params: &Arc<Mutex<Vec<()>>>) -> Self {
+        self.revert_params = params.clone();
+        self
+    }
+
+    pub fn revert_result(self, result: Result<(), String>) -> Self {
+        self.revert_results.borrow_mut().push(result);
+        self
+    }
+
+    pub fn inspect_result(self, result: Result<String, String>) -> Self {
+        self.inspect_results.borrow_mut().push(result);
+        self
+    }
+
+    pub fn manager_result(self, result: Option<String>) -> Self {
+        self.manager_results.borrow_mut().push(result);
+        self
+    }
+}
@@ -4,33 +4,52 @@ pub mod crash_notification;
 pub mod daemon_initializer;
 pub mod launch_verifier;
 mod launcher;
+pub mod restart_policy;
 mod setup_reporter;
 
 #[cfg(test)]
 mod mocks;
 
-use crate::daemon::crash_notification::CrashNotification;
+use crate::daemon::crash_notification::{last_log_lines, CrashNotification};
 use crate::daemon::launch_verifier::{VerifierTools, VerifierToolsReal};
+use crate::daemon::restart_policy::{RestartPolicy, STABLE_RUN_TIME};
 use crate::daemon::setup_reporter::{SetupCluster, SetupReporter, SetupReporterReal};
 use crate::sub_lib::logger::Logger;
 use crate::sub_lib::utils::NODE_MAILBOX_CAPACITY;
 use actix::Recipient;
-use actix::{Actor, Context, Handler, Message};
+use actix::{Actor, AsyncContext, Context, Handler, Message};
 use crossbeam_channel::{Receiver, Sender};
+use dns_utility_lib::dns_modifier::DnsModifier;
+use dns_utility_lib::dns_modifier_factory::{DnsModifierFactory, DnsModifierFactoryReal};
+use dns_utility_lib::dns_utility::status_of;
 use itertools::Itertools;
 use lazy_static::lazy_static;
 use masq_lib::messages::UiSetupResponseValueStatus::{Configured, Set};
 use masq_lib::messages::{
-    FromMessageBody, ToMessageBody, UiNodeCrashedBroadcast, UiRedirect, UiSetupBroadcast,
-    UiSetupRequest, UiSetupResponse, UiSetupResponseValue, UiStartOrder, UiStartResponse,
+    FromMessageBody, ToMessageBody, UiConfigurationSchemaRequest, UiConfigurationSchemaResponse,
+    UiDnsRevertRequest, UiDnsRevertResponse, UiDnsStatusRequest, UiDnsStatusResponse,
+    UiDnsSubvertRequest, UiDnsSubvertResponse, UiInstance, UiInstancesRequest, UiInstancesResponse,
+    UiNodeCrashedBroadcast, UiNodeRestartedBroadcast, UiRedirect, UiSetupBroadcast, UiSetupRequest,
+    UiSetupResponse, UiSetupResponseValue, UiStartOrder, UiStartResponse, DNS_ERROR,
     NODE_ALREADY_RUNNING_ERROR, NODE_LAUNCH_ERROR, NODE_NOT_RUNNING_ERROR,
 };
 use masq_lib::shared_schema::ConfiguratorError;
 use masq_lib::ui_gateway::MessagePath::{Conversation, FireAndForget};
 use masq_lib::ui_gateway::MessageTarget::ClientId;
 use masq_lib::ui_gateway::{MessageBody, MessageTarget, NodeFromUiMessage, NodeToUiMessage};
+use serde_json::Value;
 use std::collections::{HashMap, HashSet};
 use std::iter::FromIterator;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+// The Node a UI is talking about when it doesn't name one
+pub const DEFAULT_INSTANCE: &str = "default";
+
+// Nodes running side by side mustn't share any of these
+const EXCLUSIVE_PARAMS: &[&str] = &["clandestine-port", "data-directory", "ui-port"];
+
+const CRASH_LOG_LINES: usize = 20;
 
 pub struct Recipients {
     ui_gateway_from_sub: Recipient<NodeFromUiMessage>,
@@ -92,15 +111,53 @@ pub struct DaemonBindMessage {
     pub crash_notification_recipient: Recipient<CrashNotification>, // the Daemon itself, for crash notifications
 }
 
+#[derive(Message)]
+struct RestartInstance {
+    instance: String,
+}
+
+#[derive(Default)]
+struct NodeInstance {
+    params: SetupCluster,
+    process_id: Option<u32>,
+    ui_port: Option<u16>,
+    started_at: Option<Instant>,
+    // Restarts since the Node was last started by a UI or last ran long enough to count as stable
+    retries: u32,
+}
+
+impl NodeInstance {
+    fn censored_params(&self) -> SetupCluster {
+        self.params
+            .clone()
+            .into_iter()
+            .map(|(name, uisrv)| match CENSORABLES.get(&name) {
+                Some(length) => (
+                    name,
+                    UiSetupResponseValue::new(&uisrv.name, &"*".repeat(*length), uisrv.status),
+                ),
+                None => (name, uisrv),
+            })
+            .collect()
+    }
+
+    fn exclusive_value(&self, name: &str) -> Option<&str> {
+        match self.params.get(name) {
+            Some(uisrv) if !uisrv.value.is_empty() => Some(uisrv.value.as_str()),
+            _ => None,
+        }
+    }
+}
+
 pub struct Daemon {
     launcher: Box<dyn Launcher>,
-    params: SetupCluster,
+    instances: HashMap<String, NodeInstance>,
+    restart_policy: RestartPolicy,
     ui_gateway_sub: Option<Recipient<NodeToUiMessage>>,
     crash_notification_sub: Option<Recipient<CrashNotification>>,
-    node_process_id: Option<u32>,
-    node_ui_port: Option<u16>,
     verifier_tools: Box<dyn VerifierTools>,
     setup_reporter: Box<dyn SetupReporter>,
+    dns_modifier_factory: Box<dyn DnsModifierFactory>,
     logger: Logger,
 }
 
@@ -131,8 +188,22 @@ impl Handler<NodeFromUiMessage> for Daemon {
         let client_id = msg.client_id;
         if let Ok((setup_request, context_id)) = UiSetupRequest::fmb(msg.body.clone()) {
             self.handle_setup(client_id, context_id, setup_request);
-        } else if let Ok((_, context_id)) = UiStartOrder::fmb(msg.body.clone()) {
-            self.handle_start_order(client_id, context_id);
+        } else if let Ok((start_order, context_id)) = UiStartOrder::fmb(msg.body.clone()) {
+            self.handle_start_order(
+                client_id,
+                context_id,
+                instance_name(start_order.instance_opt),
+            );
+        } else if let Ok((_, context_id)) = UiInstancesRequest::fmb(msg.body.clone()) {
+            self.handle_instances_request(client_id, context_id);
+        } else if let Ok((_, context_id)) = UiConfigurationSchemaRequest::fmb(msg.body.clone()) {
+            self.handle_configuration_schema_request(client_id, context_id);
+        } else if let Ok((_, context_id)) = UiDnsStatusRequest::fmb(msg.body.clone()) {
+            self.handle_dns_status(client_id, context_id);
+        } else if let Ok((_, context_id)) = UiDnsSubvertRequest::fmb(msg.body.clone()) {
+            self.handle_dns_subvert(client_id, context_id);
+        } else if let Ok((_, context_id)) = UiDnsRevertRequest::fmb(msg.body.clone()) {
+            self.handle_dns_revert(client_id, context_id);
         } else {
             self.handle_unexpected_message(client_id, msg.body);
         }
@@ -143,57 +214,86 @@ impl Handler<NodeFromUiMessage> for Daemon {
 impl Handler<CrashNotification> for Daemon {
     type Result = ();
 
-    fn handle(&mut self, msg: CrashNotification, _ctx: &mut Self::Context) -> Self::Result {
+    fn handle(&mut self, msg: CrashNotification, ctx: &mut Self::Context) -> Self::Result {
         debug!(&self.logger, "Handling CrashNotification");
-        self.handle_crash_notification(msg);
+        if let Some((instance, delay)) = self.handle_crash_notification(msg) {
+            ctx.notify_later(RestartInstance { instance }, delay);
+        }
         debug!(&self.logger, "CrashNotification handled");
     }
 }
 
+impl Handler<RestartInstance> for Daemon {
+    type Result = ();
+
+    fn handle(&mut self, msg: RestartInstance, ctx: &mut Self::Context) -> Self::Result {
+        debug!(&self.logger, "Handling RestartInstance");
+        if let Some(delay) = self.handle_restart(&msg.instance) {
+            ctx.notify_later(msg, delay);
+        }
+        debug!(&self.logger, "RestartInstance handled");
+    }
+}
+
 impl Daemon {
-    pub fn new(launcher: Box<dyn Launcher>) -> Daemon {
+    pub fn new(launcher: Box<dyn Launcher>, restart_policy: RestartPolicy) -> Daemon {
         Daemon {
             launcher,
-            params: HashMap::new(),
+            restart_policy,
+            instances: vec![(DEFAULT_INSTANCE.to_string(), NodeInstance::default())]
+                .into_iter()
+                .collect(),
             ui_gateway_sub: None,
             crash_notification_sub: None,
-            node_process_id: None,
-            node_ui_port: None,
             verifier_tools: Box::new(VerifierToolsReal::new()),
             setup_reporter: Box::new(SetupReporterReal::new()),
+            dns_modifier_factory: Box::new(DnsModifierFactoryReal::new()),
             logger: Logger::new("Daemon"),
         }
     }
 
     fn handle_setup(&mut self, client_id: u64, context_id: u64, payload: UiSetupRequest) {
-        if self.port_if_node_is_running().is_some() {
-            let body =
-                UiSetupResponse::new(true, self.censored_params(), ConfiguratorError::new(vec![]))
-                    .tmb(context_id);
+        let instance = instance_name(payload.instance_opt);
+        if self.port_if_node_is_running(&instance).is_some() {
+            let body = UiSetupResponse::new(
+                true,
+                self.instance_mut(&instance).censored_params(),
+                ConfiguratorError::new(vec![]),
+                Some(instance),
+            )
+            .tmb(context_id);
             let target = MessageTarget::ClientId(client_id);
             self.send_ui_message(body, target);
         } else {
             let incoming_setup = payload.values;
-            let existing_setup = self.params.clone();
+            let existing_setup = match self.instances.get(&instance) {
+                Some(node_instance) => node_instance.params.clone(),
+                None => HashMap::new(),
+            };
             match self
                 .setup_reporter
                 .get_modified_setup(existing_setup, incoming_setup)
             {
                 Ok(setup) => self.change_setup_and_notify(
+                    &instance,
                     setup,
                     ConfiguratorError::new(vec![]),
                     client_id,
                     context_id,
                 ),
-                Err((lame_cluster, errors)) => {
-                    self.change_setup_and_notify(lame_cluster, errors, client_id, context_id)
-                }
+                Err((lame_cluster, errors)) => self.change_setup_and_notify(
+                    &instance,
+                    lame_cluster,
+                    errors,
+                    client_id,
+                    context_id,
+                ),
             }
         };
     }
 
-    fn handle_start_order(&mut self, client_id: u64, context_id: u64) {
-        match self.port_if_node_is_running() {
+    fn handle_start_order(&mut self, client_id: u64, context_id: u64, instance: String) {
+        match self.port_if_node_is_running(&instance) {
             Some(_) => self.respond_to_ui(
                 client_id,
                 MessageBody {
@@ -205,47 +305,216 @@ impl Daemon {
                     )),
                 },
             ),
-            None => match self.launcher.launch(
-                self.params
-                    .iter()
-                    .filter(|(_, v)| v.status == Set || v.status == Configured)
-                    .map(|(k, v)| (k.to_string(), v.value.to_string()))
-                    .collect(),
-                self.crash_notification_sub.clone().expect("Daemon unbound"),
-            ) {
+            None => match self.launch_obstacle(&instance) {
+                Some(obstacle) => self.send_launch_error(client_id, context_id, obstacle),
+                None => self.launch(client_id, context_id, &instance),
+            },
+        }
+    }
+
+    fn launch(&mut self, client_id: u64, context_id: u64, instance: &str) {
+        match self.launch_instance(instance) {
+            Ok(Some(success)) => {
+                self.instance_mut(instance).retries = 0;
+                self.respond_to_ui(
+                    client_id,
+                    UiStartResponse {
+                        new_process_id: success.new_process_id,
+                        redirect_ui_port: success.redirect_ui_port,
+                    }
+                    .tmb(context_id),
+                )
+            }
+            Ok(None) => (),
+            Err(s) => self.send_launch_error(client_id, context_id, s),
+        }
+    }
+
+    fn launch_instance(&mut self, instance: &str) -> Result<Option<LaunchSuccess>, String> {
+        let result = self.launcher.launch(
+            self.instances[instance]
+                .params
+                .iter()
+                .filter(|(_, v)| v.status == Set || v.status == Configured)
+                .map(|(k, v)| (k.to_string(), v.value.to_string()))
+                .collect(),
+            self.crash_notification_sub.clone().expect("Daemon unbound"),
+        );
+        if let Ok(Some(success)) = &result {
+            let node_instance = self.instance_mut(instance);
+            node_instance.process_id = Some(success.new_process_id);
+            node_instance.ui_port = Some(success.redirect_ui_port);
+            node_instance.started_at = Some(Instant::now());
+        }
+        result
+    }
+
+    // Returns how long to wait before trying again, if the restart fails and the policy allows another
+    fn handle_restart(&mut self, instance: &str) -> Option<Duration> {
+        if self.port_if_node_is_running(instance).is_some() {
+            return None; // a UI has started it in the meantime
+        }
+        let failure = match self.launch_obstacle(instance) {
+            Some(obstacle) => obstacle,
+            None => match self.launch_instance(instance) {
                 Ok(Some(success)) => {
-                    self.node_process_id = Some(success.new_process_id);
-                    self.node_ui_port = Some(success.redirect_ui_port);
-                    self.respond_to_ui(
-                        client_id,
-                        UiStartResponse {
+                    info!(
+                        &self.logger,
+                        "Restarted Node '{}' as process {}", instance, success.new_process_id
+                    );
+                    let attempt = self.instance_mut(instance).retries;
+                    self.send_ui_message(
+                        UiNodeRestartedBroadcast {
                             new_process_id: success.new_process_id,
                             redirect_ui_port: success.redirect_ui_port,
+                            attempt,
+                            instance_opt: Some(instance.to_string()),
                         }
-                        .tmb(context_id),
-                    )
+                        .tmb(0),
+                        MessageTarget::AllClients,
+                    );
+                    return None;
                 }
-                Ok(None) => (),
-                Err(s) => self.respond_to_ui(
-                    client_id,
-                    MessageBody {
-                        opcode: "start".to_string(),
-                        path: Conversation(context_id),
-                        payload: Err((NODE_LAUNCH_ERROR, format!("Could not launch Node: {}", s))),
-                    },
-                ),
+                Ok(None) => return None,
+                Err(s) => s,
             },
+        };
+        let node_instance = self.instance_mut(instance);
+        node_instance.retries += 1;
+        let retry = node_instance.retries;
+        let delay_opt = self.restart_policy.delay_before_retry(retry);
+        match delay_opt {
+            Some(delay) => warning!(
+                &self.logger,
+                "Couldn't restart Node '{}': {}; trying again in {}s",
+                instance,
+                failure,
+                delay.as_secs()
+            ),
+            None => error!(
+                &self.logger,
+                "Couldn't restart Node '{}': {}; giving up", instance, failure
+            ),
+        }
+        delay_opt
+    }
+
+    fn handle_instances_request(&mut self, client_id: u64, context_id: u64) {
+        let names = self.instances.keys().cloned().sorted().collect_vec();
+        let instances = names
+            .into_iter()
+            .map(|name| {
+                let running = self.port_if_node_is_running(&name).is_some();
+                let node_instance = self.instance_mut(&name);
+                UiInstance {
+                    running,
+                    process_id_opt: node_instance.process_id,
+                    ui_port_opt: node_instance.ui_port,
+                    name,
+                }
+            })
+            .collect();
+        self.respond_to_ui(client_id, UiInstancesResponse { instances }.tmb(context_id));
+    }
+
+    fn handle_configuration_schema_request(&self, client_id: u64, context_id: u64) {
+        let parameters = SetupReporterReal::get_configuration_schema();
+        self.respond_to_ui(
+            client_id,
+            UiConfigurationSchemaResponse { parameters }.tmb(context_id),
+        );
+    }
+
+    fn handle_dns_status(&self, client_id: u64, context_id: u64) {
+        let body = match self.inspect_dns() {
+            Ok(response) => response.tmb(context_id),
+            Err(e) => Self::dns_error("dnsStatus", context_id, "read", e),
+        };
+        self.respond_to_ui(client_id, body);
+    }
+
+    fn handle_dns_subvert(&self, client_id: u64, context_id: u64) {
+        let body = match self.make_dns_modifier().and_then(|modifier| {
+            if let Some(manager) = modifier.manager() {
+                warning!(
+                    &self.logger,
+                    "Subverting DNS settings managed by {}, which may undo it",
+                    manager
+                );
+            }
+            modifier.subvert()
+        }) {
+            Ok(()) => {
+                info!(
+                    &self.logger,
+                    "DNS subverted at the request of UI {}", client_id
+                );
+                UiDnsSubvertResponse {}.tmb(context_id)
+            }
+            Err(e) => Self::dns_error("dnsSubvert", context_id, "subvert", e),
+        };
+        self.respond_to_ui(client_id, body);
+    }
+
+    fn handle_dns_revert(&self, client_id: u64, context_id: u64) {
+        let body = match self
+            .make_dns_modifier()
+            .and_then(|modifier| modifier.revert())
+        {
+            Ok(()) => {
+                info!(
+                    &self.logger,
+                    "DNS reverted at the request of UI {}", client_id
+                );
+                UiDnsRevertResponse {}.tmb(context_id)
+            }
+            Err(e) => Self::dns_error("dnsRevert", context_id, "revert", e),
+        };
+        self.respond_to_ui(client_id, body);
+    }
+
+    fn inspect_dns(&self) -> Result<UiDnsStatusResponse, String> {
+        let modifier = self.make_dns_modifier()?;
+        let mut buffer: Vec<u8> = vec![];
+        modifier.inspect(&mut buffer)?;
+        let dns_server_list = String::from_utf8(buffer).map_err(|e| e.to_string())?;
+        Ok(UiDnsStatusResponse {
+            status: status_of(&dns_server_list).to_string(),
+            nameservers: dns_server_list
+                .lines()
+                .map(|line| line.to_string())
+                .collect(),
+            managed_by_opt: modifier.manager(),
+        })
+    }
+
+    fn make_dns_modifier(&self) -> Result<Box<dyn DnsModifier>, String> {
+        self.dns_modifier_factory
+            .make()
+            .ok_or_else(|| "Don't know how to modify DNS settings on this system".to_string())
+    }
+
+    fn dns_error(opcode: &str, context_id: u64, action: &str, msg: String) -> MessageBody {
+        MessageBody {
+            opcode: opcode.to_string(),
+            path: Conversation(context_id),
+            payload: Err((
+                DNS_ERROR,
+                format!("Cannot {} DNS settings: {}", action, msg),
+            )),
         }
     }
 
     fn handle_unexpected_message(&mut self, client_id: u64, body: MessageBody) {
-        match self.port_if_node_is_running() {
+        let instance = instance_name(instance_selected_by(&body));
+        match self.port_if_node_is_running(&instance) {
             Some(port) => {
                 info!(
                     &self.logger,
-                    "Daemon is redirecting {} message from UI {} Node at port {}",
+                    "Daemon is redirecting {} message from UI {} Node '{}' at port {}",
                     body.opcode,
                     client_id,
+                    instance,
                     port
                 );
                 self.send_ui_message(
@@ -269,31 +538,67 @@ impl Daemon {
         }
     }
 
-    fn handle_crash_notification(&mut self, msg: CrashNotification) {
-        if self.node_ui_port.is_some() || self.node_process_id.is_some() {
-            self.node_process_id = None;
-            self.node_ui_port = None;
-            self.send_ui_message(
-                UiNodeCrashedBroadcast {
-                    process_id: msg.process_id,
-                    crash_reason: msg.analyze(),
-                }
-                .tmb(0),
-                MessageTarget::AllClients,
-            );
+    // Returns the instance to restart and how long to wait first, if the policy calls for it
+    fn handle_crash_notification(&mut self, msg: CrashNotification) -> Option<(String, Duration)> {
+        let (name, node_instance) = self
+            .instances
+            .iter_mut()
+            .find(|(_, node_instance)| node_instance.process_id == Some(msg.process_id))?;
+        let instance = name.clone();
+        node_instance.process_id = None;
+        node_instance.ui_port = None;
+        match node_instance.started_at.take() {
+            Some(started_at) if started_at.elapsed() >= STABLE_RUN_TIME => {
+                node_instance.retries = 0
+            }
+            _ => (),
         }
+        // A Node that exits cleanly was told to shut down, so it stays down
+        let delay_opt = if msg.exit_code == Some(0) {
+            None
+        } else {
+            node_instance.retries += 1;
+            self.restart_policy
+                .delay_before_retry(node_instance.retries)
+        };
+        let last_log_lines = match node_instance.exclusive_value("data-directory") {
+            Some(data_directory) => last_log_lines(Path::new(data_directory), CRASH_LOG_LINES),
+            None => vec![],
+        };
+        error!(
+            &self.logger,
+            "Node '{}' (process {}) terminated with exit code {:?}",
+            instance,
+            msg.process_id,
+            msg.exit_code
+        );
+        self.send_ui_message(
+            UiNodeCrashedBroadcast {
+                process_id: msg.process_id,
+                crash_reason: msg.analyze(),
+                exit_code_opt: msg.exit_code,
+                last_log_lines,
+                restart_delay_secs_opt: delay_opt.map(|delay| delay.as_secs()),
+                instance_opt: Some(instance.clone()),
+            }
+            .tmb(0),
+            MessageTarget::AllClients,
+        );
+        delay_opt.map(|delay| (instance, delay))
     }
 
-    fn port_if_node_is_running(&mut self) -> Option<u16> {
-        if let Some(process_id) = self.node_process_id {
+    fn port_if_node_is_running(&mut self, instance: &str) -> Option<u16> {
+        let node_instance = self.instances.get_mut(instance)?;
+        if let Some(process_id) = node_instance.process_id {
             if self.verifier_tools.process_is_running(process_id) {
                 Some(
-                    self.node_ui_port
-                        .expect("Internal error: node_process_id is set but node_ui_port is not"),
+                    node_instance
+                        .ui_port
+                        .expect("Internal error: process_id is set but ui_port is not"),
                 )
             } else {
-                self.node_process_id = None;
-                self.node_ui_port = None;
+                node_instance.process_id = None;
+                node_instance.ui_port = None;
                 None
             }
         } else {
@@ -301,6 +606,61 @@ impl Daemon {
         }
     }
 
+    fn launch_obstacle(&mut self, instance: &str) -> Option<String> {
+        if !self.instances.contains_key(instance) {
+            return Some(format!(
+                "there is no instance named '{}'; set it up first",
+                instance
+            ));
+        }
+        let others = self
+            .instances
+            .keys()
+            .filter(|name| *name != instance)
+            .cloned()
+            .sorted()
+            .collect_vec();
+        let running_others = others
+            .into_iter()
+            .filter(|other| self.port_if_node_is_running(other).is_some())
+            .collect_vec();
+        let mine = &self.instances[instance];
+        running_others.into_iter().find_map(|other| {
+            let theirs = &self.instances[&other];
+            EXCLUSIVE_PARAMS.iter().find_map(|param| {
+                match (mine.exclusive_value(param), theirs.exclusive_value(param)) {
+                    (Some(my_value), Some(their_value)) if my_value == their_value => {
+                        Some(format!(
+                            "instance '{}' is already running with {} {}",
+                            other, param, my_value
+                        ))
+                    }
+                    _ => None,
+                }
+            })
+        })
+    }
+
+    fn instance_mut(&mut self, instance: &str) -> &mut NodeInstance {
+        self.instances
+            .entry(instance.to_string())
+            .or_insert_with(NodeInstance::default)
+    }
+
+    fn send_launch_error(&self, client_id: u64, context_id: u64, reason: String) {
+        self.respond_to_ui(
+            client_id,
+            MessageBody {
+                opcode: "start".to_string(),
+                path: Conversation(context_id),
+                payload: Err((
+                    NODE_LAUNCH_ERROR,
+                    format!("Could not launch Node: {}", reason),
+                )),
+            },
+        )
+    }
+
     fn send_node_is_not_running_error(&self, client_id: u64, err_opcode: String) {
         error!(
             &self.logger,
@@ -326,52 +686,59 @@ impl Daemon {
 
     fn change_setup_and_notify(
         &mut self,
+        instance: &str,
         new_setup: SetupCluster,
         errors: ConfiguratorError,
         client_id: u64,
         context_id: u64,
     ) {
-        let body_target_pairs = match Self::compare_setup_clusters(&self.params, &new_setup) {
-            Err(_) => {
-                let originally_empty = self.params.is_empty();
-                self.params = new_setup;
-                let mut pairs = vec![(
-                    UiSetupResponse::new(false, self.censored_params(), errors.clone())
+        let instance_opt = Some(instance.to_string());
+        let node_instance = self.instance_mut(instance);
+        let body_target_pairs =
+            match Self::compare_setup_clusters(&node_instance.params, &new_setup) {
+                Err(_) => {
+                    let originally_empty = node_instance.params.is_empty();
+                    node_instance.params = new_setup;
+                    let mut pairs = vec![(
+                        UiSetupResponse::new(
+                            false,
+                            node_instance.censored_params(),
+                            errors.clone(),
+                            instance_opt.clone(),
+                        )
                         .tmb(context_id),
+                        MessageTarget::ClientId(client_id),
+                    )];
+                    if !originally_empty {
+                        pairs.push((
+                            UiSetupBroadcast::new(
+                                false,
+                                node_instance.censored_params(),
+                                errors,
+                                instance_opt,
+                            )
+                            .tmb(0),
+                            MessageTarget::AllExcept(client_id),
+                        ));
+                    };
+                    pairs
+                }
+                Ok(_) => vec![(
+                    UiSetupResponse::new(
+                        false,
+                        node_instance.censored_params(),
+                        errors,
+                        instance_opt,
+                    )
+                    .tmb(context_id),
                     MessageTarget::ClientId(client_id),
-                )];
-                if !originally_empty {
-                    pairs.push((
-                        UiSetupBroadcast::new(false, self.censored_params(), errors).tmb(0),
-                        MessageTarget::AllExcept(client_id),
-                    ));
-                };
-                pairs
-            }
-            Ok(_) => vec![(
-                UiSetupResponse::new(false, self.censored_params(), errors).tmb(context_id),
-                MessageTarget::ClientId(client_id),
-            )],
-        };
+                )],
+            };
         body_target_pairs
             .into_iter()
             .for_each(|(body, target)| self.send_ui_message(body, target));
     }
 
-    fn censored_params(&self) -> SetupCluster {
-        self.params
-            .clone()
-            .into_iter()
-            .map(|(name, uisrv)| match CENSORABLES.get(&name) {
-                Some(length) => (
-                    name,
-                    UiSetupResponseValue::new(&uisrv.name, &"*".repeat(*length), uisrv.status),
-                ),
-                None => (name, uisrv),
-            })
-            .collect()
-    }
-
     fn send_ui_message(&self, body: MessageBody, target: MessageTarget) {
         self.ui_gateway_sub
             .as_ref()
@@ -421,23 +788,41 @@ impl Daemon {
     }
 }
 
+fn instance_name(instance_opt: Option<String>) -> String {
+    instance_opt.unwrap_or_else(|| DEFAULT_INSTANCE.to_string())
+}
+
+// Messages meant for a Node can pick it out with an "instance" field in their payloads
+fn instance_selected_by(body: &MessageBody) -> Option<String> {
+    let payload = body.payload.as_ref().ok()?;
+    match serde_json::from_str::<Value>(payload) {
+        Ok(json) => json
+            .get("instance")
+            .and_then(|instance| instance.as_str())
+            .map(|instance| instance.to_string()),
+        Err(_) => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::daemon::crash_notification::CrashNotification;
-    use crate::daemon::mocks::VerifierToolsMock;
+    use crate::daemon::mocks::{DnsModifierFactoryMock, DnsModifierMock, VerifierToolsMock};
     use crate::daemon::setup_reporter::{setup_cluster_from, SetupCluster};
     use crate::daemon::LaunchSuccess;
+    use crate::test_utils::logging::{init_test_logging, TestLogHandler};
     use crate::test_utils::recorder::{make_recorder, Recorder};
     use actix::System;
+    use masq_lib::constants::CURRENT_LOGFILE_NAME;
     use masq_lib::messages::UiSetupResponseValueStatus::{Blank, Required, Set};
     use masq_lib::messages::{
         CrashReason, UiFinancialsRequest, UiNodeCrashedBroadcast, UiRedirect, UiSetupBroadcast,
-        UiSetupRequest, UiSetupRequestValue, UiSetupResponse, UiSetupResponseValue,
-        UiSetupResponseValueStatus, UiShutdownRequest, UiStartOrder, UiStartResponse,
-        NODE_ALREADY_RUNNING_ERROR, NODE_LAUNCH_ERROR, NODE_NOT_RUNNING_ERROR,
+        UiSetupRequest, UiSetupRequestValue, UiSetupResponse, UiSetupResponseError,
+        UiSetupResponseValue, UiSetupResponseValueStatus, UiShutdownRequest, UiStartOrder,
+        UiStartResponse, NODE_ALREADY_RUNNING_ERROR, NODE_LAUNCH_ERROR, NODE_NOT_RUNNING_ERROR,
     };
-    use masq_lib::shared_schema::ConfiguratorError;
+    use masq_lib::shared_schema::{ConfiguratorError, ParamErrorCode};
     use masq_lib::test_utils::environment_guard::{ClapGuard, EnvironmentGuard};
     use masq_lib::test_utils::utils::{ensure_node_home_directory_exists, TEST_DEFAULT_CHAIN_NAME};
     use masq_lib::ui_gateway::MessageTarget::AllExcept;
@@ -561,15 +946,15 @@ mod tests {
 
     #[test]
     fn censorship_works() {
-        let mut subject = Daemon::new(Box::new(LauncherMock::new()));
-        subject.params = make_setup_cluster(vec![
+        let mut subject = Daemon::new(Box::new(LauncherMock::new()), RestartPolicy::default());
+        subject.instance_mut(DEFAULT_INSTANCE).params = make_setup_cluster(vec![
             ("one-non-censorable", "one value", Set),
             ("db-password", "super-secret value", Configured),
             ("consuming-private-key", "another super-secret value", Blank),
             ("another-non-censorable", "another value", Required),
         ]);
 
-        let result = subject.censored_params();
+        let result = subject.instance_mut(DEFAULT_INSTANCE).censored_params();
 
         assert_eq!(
             result,
@@ -592,16 +977,16 @@ mod tests {
         let verifier_tools = VerifierToolsMock::new().process_is_running_result(true);
         let setup_reporter = SetupReporterMock::new(); // will panic if called
         let system = System::new("test");
-        let mut subject = Daemon::new(Box::new(LauncherMock::new()));
+        let mut subject = Daemon::new(Box::new(LauncherMock::new()), RestartPolicy::default());
         subject.verifier_tools = Box::new(verifier_tools);
         subject.setup_reporter = Box::new(setup_reporter);
-        subject.params = make_setup_cluster(vec![
+        subject.instance_mut(DEFAULT_INSTANCE).params = make_setup_cluster(vec![
             ("neighborhood-mode", "zero-hop", Set),
             ("consuming-private-key", "secret value", Set),
             ("db-password", "secret value", Set),
         ]);
-        subject.node_process_id = Some(12345);
-        subject.node_ui_port = Some(54321);
+        subject.instance_mut(DEFAULT_INSTANCE).process_id = Some(12345);
+        subject.instance_mut(DEFAULT_INSTANCE).ui_port = Some(54321);
         let subject_addr = subject.start();
         subject_addr
             .try_send(make_bind_message(ui_gateway))
@@ -612,6 +997,7 @@ mod tests {
                 client_id: 1234,
                 body: UiSetupRequest {
                     values: vec![UiSetupRequestValue::new("log-level", "trace")],
+                    instance_opt: None,
                 }
                 .tmb(4321),
             })
@@ -641,6 +1027,7 @@ mod tests {
                     UiSetupResponseValue::new("neighborhood-mode", "zero-hop", Set),
                 ],
                 errors: vec![],
+                instance_opt: Some(DEFAULT_INSTANCE.to_string()),
             }
         );
     }
@@ -657,15 +1044,15 @@ mod tests {
         ]);
         let setup_reporter = SetupReporterMock::new().get_modified_setup_result(Ok(combined_setup));
         let system = System::new("test");
-        let mut subject = Daemon::new(Box::new(LauncherMock::new()));
+        let mut subject = Daemon::new(Box::new(LauncherMock::new()), RestartPolicy::default());
         subject.verifier_tools = Box::new(verifier_tools);
         subject.setup_reporter = Box::new(setup_reporter);
-        subject.params = make_setup_cluster(vec![
+        subject.instance_mut(DEFAULT_INSTANCE).params = make_setup_cluster(vec![
             ("neighborhood-mode", "zero-hop", Set),
             ("db-password", "secret value", Set),
         ]);
-        subject.node_process_id = None;
-        subject.node_ui_port = None;
+        subject.instance_mut(DEFAULT_INSTANCE).process_id = None;
+        subject.instance_mut(DEFAULT_INSTANCE).ui_port = None;
         let subject_addr = subject.start();
         subject_addr
             .try_send(make_bind_message(ui_gateway))
@@ -679,6 +1066,7 @@ mod tests {
                         UiSetupRequestValue::new("log-level", "trace"),
                         UiSetupRequestValue::new("consuming-private-key", "secret value"),
                     ],
+                    instance_opt: None,
                 }
                 .tmb(4321),
             })
@@ -726,9 +1114,9 @@ mod tests {
         let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
         let system = System::new("test");
         let verifier_tools = VerifierToolsMock::new();
-        let mut subject = Daemon::new(Box::new(LauncherMock::new()));
-        subject.node_ui_port = None;
-        subject.node_process_id = None;
+        let mut subject = Daemon::new(Box::new(LauncherMock::new()), RestartPolicy::default());
+        subject.instance_mut(DEFAULT_INSTANCE).ui_port = None;
+        subject.instance_mut(DEFAULT_INSTANCE).process_id = None;
         subject.verifier_tools = Box::new(verifier_tools);
         let subject_addr = subject.start();
         subject_addr
@@ -747,6 +1135,7 @@ mod tests {
                         UiSetupRequestValue::new("chain", TEST_DEFAULT_CHAIN_NAME),
                         UiSetupRequestValue::new("neighborhood-mode", "zero-hop"),
                     ],
+                    instance_opt: None,
                 }
                 .tmb(4321),
             })
@@ -787,9 +1176,9 @@ mod tests {
         let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
         let system = System::new("test");
         let verifier_tools = VerifierToolsMock::new().process_is_running_result(false); // only consulted once; second time, we already know
-        let mut subject = Daemon::new(Box::new(LauncherMock::new()));
-        subject.node_ui_port = Some(1234);
-        subject.node_process_id = Some(4321);
+        let mut subject = Daemon::new(Box::new(LauncherMock::new()), RestartPolicy::default());
+        subject.instance_mut(DEFAULT_INSTANCE).ui_port = Some(1234);
+        subject.instance_mut(DEFAULT_INSTANCE).process_id = Some(4321);
         subject.verifier_tools = Box::new(verifier_tools);
         let subject_addr = subject.start();
         subject_addr
@@ -803,6 +1192,7 @@ mod tests {
                     UiSetupRequestValue::new("chain", TEST_DEFAULT_CHAIN_NAME),
                     UiSetupRequestValue::new("neighborhood-mode", "zero-hop"),
                 ],
+                instance_opt: None,
             }
             .tmb(4321),
         };
@@ -820,7 +1210,7 @@ mod tests {
         };
         let check_payload = |running: bool,
                              values: Vec<UiSetupResponseValue>,
-                             errors: Vec<(String, String)>| {
+                             errors: Vec<UiSetupResponseError>| {
             assert_eq!(running, false);
             let actual_pairs: HashSet<(String, String)> = values
                 .into_iter()
@@ -855,7 +1245,7 @@ mod tests {
         )]
         .into_iter()
         .collect::<SetupCluster>();
-        let mut subject = Daemon::new(Box::new(LauncherMock::new()));
+        let mut subject = Daemon::new(Box::new(LauncherMock::new()), RestartPolicy::default());
         subject.setup_reporter =
             Box::new(SetupReporterMock::new().get_modified_setup_result(Err((
                 lame_setup,
@@ -877,7 +1267,12 @@ mod tests {
                 body: UiSetupResponse {
                     running: false,
                     values: vec![UiSetupResponseValue::new("name", "value", Configured)],
-                    errors: vec![("parameter".to_string(), "message".to_string())]
+                    errors: vec![UiSetupResponseError::new(
+                        "parameter",
+                        ParamErrorCode::Invalid,
+                        "message"
+                    )],
+                    instance_opt: Some(DEFAULT_INSTANCE.to_string()),
                 }
                 .tmb(74),
             }
@@ -887,9 +1282,9 @@ mod tests {
     #[test]
     fn handle_setup_responds_but_does_not_broadcast_if_setup_changes_from_nothing() {
         let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
-        let mut subject = Daemon::new(Box::new(LauncherMock::new()));
-        subject.params.clear(); // nothing
-        let existing_setup = subject.params.clone();
+        let mut subject = Daemon::new(Box::new(LauncherMock::new()), RestartPolicy::default());
+        subject.instance_mut(DEFAULT_INSTANCE).params.clear(); // nothing
+        let existing_setup = subject.instance_mut(DEFAULT_INSTANCE).params.clone();
         let modified_setup = {
             let mut modified_setup = existing_setup.clone();
             modified_setup.insert(
@@ -921,7 +1316,8 @@ mod tests {
                         .map(|(_, v)| v)
                         .map(|v| v.clone())
                         .collect(),
-                    errors: vec![]
+                    errors: vec![],
+                    instance_opt: Some(DEFAULT_INSTANCE.to_string()),
                 }
                 .tmb(74),
             }
@@ -932,8 +1328,8 @@ mod tests {
     #[test]
     fn handle_setup_responds_but_does_not_broadcast_if_setup_is_not_changed() {
         let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
-        let mut subject = Daemon::new(Box::new(LauncherMock::new()));
-        subject.params.insert(
+        let mut subject = Daemon::new(Box::new(LauncherMock::new()), RestartPolicy::default());
+        subject.instance_mut(DEFAULT_INSTANCE).params.insert(
             "db-password".to_string(),
             UiSetupResponseValue::new("db-password", "secret value", Configured),
         ); // not nothing
@@ -968,7 +1364,8 @@ mod tests {
                     ),]
                     .into_iter()
                     .collect(),
-                    errors: vec![]
+                    errors: vec![],
+                    instance_opt: Some(DEFAULT_INSTANCE.to_string()),
                 }
                 .tmb(74),
             }
@@ -979,12 +1376,12 @@ mod tests {
     #[test]
     fn handle_setup_responds_and_broadcasts_if_setup_is_changed() {
         let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
-        let mut subject = Daemon::new(Box::new(LauncherMock::new()));
-        subject.params.insert(
+        let mut subject = Daemon::new(Box::new(LauncherMock::new()), RestartPolicy::default());
+        subject.instance_mut(DEFAULT_INSTANCE).params.insert(
             "booga".to_string(),
             UiSetupResponseValue::new("booga", "agoob", Configured),
         ); // not nothing
-        let existing_setup = subject.params.clone();
+        let existing_setup = subject.instance_mut(DEFAULT_INSTANCE).params.clone();
         let modified_setup = {
             let mut modified_setup = existing_setup.clone();
             modified_setup.insert(
@@ -1016,7 +1413,8 @@ mod tests {
                         .map(|(_, v)| v.clone())
                         .sorted_by(|a, b| Ord::cmp(&a.name, &b.name))
                         .collect(),
-                    errors: vec![]
+                    errors: vec![],
+                    instance_opt: Some(DEFAULT_INSTANCE.to_string()),
                 }
                 .tmb(74),
             }
@@ -1033,7 +1431,8 @@ mod tests {
                         .map(|(_, v)| v)
                         .sorted_by(|a, b| Ord::cmp(&a.name, &b.name))
                         .collect(),
-                    errors: vec![]
+                    errors: vec![],
+                    instance_opt: Some(DEFAULT_INSTANCE.to_string()),
                 }
                 .tmb(0),
             }
@@ -1052,8 +1451,8 @@ mod tests {
             })));
         let verifier_tools = VerifierToolsMock::new();
         let system = System::new("test");
-        let mut subject = Daemon::new(Box::new(launcher));
-        subject.params.insert(
+        let mut subject = Daemon::new(Box::new(launcher), RestartPolicy::default());
+        subject.instance_mut(DEFAULT_INSTANCE).params.insert(
             "db-password".to_string(),
             UiSetupResponseValue::new("db-password", "goober", Set),
         );
@@ -1066,7 +1465,7 @@ mod tests {
         subject_addr
             .try_send(NodeFromUiMessage {
                 client_id: 1234,
-                body: UiStartOrder {}.tmb(4321),
+                body: UiStartOrder { instance_opt: None }.tmb(4321),
             })
             .unwrap();
 
@@ -1107,8 +1506,8 @@ mod tests {
         let launcher = LauncherMock::new().launch_result(Ok(None));
         let verifier_tools = VerifierToolsMock::new();
         let system = System::new("test");
-        let mut subject = Daemon::new(Box::new(launcher));
-        subject.params.insert(
+        let mut subject = Daemon::new(Box::new(launcher), RestartPolicy::default());
+        subject.instance_mut(DEFAULT_INSTANCE).params.insert(
             "db-password".to_string(),
             UiSetupResponseValue::new("db-password", "goober", Set),
         );
@@ -1121,7 +1520,7 @@ mod tests {
         subject_addr
             .try_send(NodeFromUiMessage {
                 client_id: 1234,
-                body: UiStartOrder {}.tmb(4321),
+                body: UiStartOrder { instance_opt: None }.tmb(4321),
             })
             .unwrap();
 
@@ -1149,16 +1548,16 @@ mod tests {
             .process_is_running_result(false)
             .process_is_running_result(false);
         let system = System::new("test");
-        let mut subject = Daemon::new(Box::new(launcher));
-        subject.params.insert(
+        let mut subject = Daemon::new(Box::new(launcher), RestartPolicy::default());
+        subject.instance_mut(DEFAULT_INSTANCE).params.insert(
             "db-password".to_string(),
             UiSetupResponseValue::new("db-password", "goober", Set),
         );
-        subject.params.insert(
+        subject.instance_mut(DEFAULT_INSTANCE).params.insert(
             "neighborhood-mode".to_string(),
             UiSetupResponseValue::new("neighborhood-mode", "zero-hop", Set),
         );
-        subject.params.insert(
+        subject.instance_mut(DEFAULT_INSTANCE).params.insert(
             "data-directory".to_string(),
             UiSetupResponseValue::new(
                 "data-directory",
@@ -1175,19 +1574,27 @@ mod tests {
         subject_addr
             .try_send(NodeFromUiMessage {
                 client_id: 1234,
-                body: UiSetupRequest { values: vec![] }.tmb(4321),
+                body: UiSetupRequest {
+                    values: vec![],
+                    instance_opt: None,
+                }
+                .tmb(4321),
             })
             .unwrap();
         subject_addr
             .try_send(NodeFromUiMessage {
                 client_id: 1234,
-                body: UiStartOrder {}.tmb(4321),
+                body: UiStartOrder { instance_opt: None }.tmb(4321),
             })
             .unwrap();
         subject_addr
             .try_send(NodeFromUiMessage {
                 client_id: 1234,
-                body: UiSetupRequest { values: vec![] }.tmb(4321),
+                body: UiSetupRequest {
+                    values: vec![],
+                    instance_opt: None,
+                }
+                .tmb(4321),
             })
             .unwrap();
 
@@ -1221,8 +1628,8 @@ mod tests {
         let launcher = LauncherMock::new().launch_result(Err("booga".to_string()));
         let verifier_tools = VerifierToolsMock::new();
         let system = System::new("test");
-        let mut subject = Daemon::new(Box::new(launcher));
-        subject.params.insert(
+        let mut subject = Daemon::new(Box::new(launcher), RestartPolicy::default());
+        subject.instance_mut(DEFAULT_INSTANCE).params.insert(
             "db-password".to_string(),
             UiSetupResponseValue::new("db-password", "goober", Set),
         );
@@ -1235,7 +1642,7 @@ mod tests {
         subject_addr
             .try_send(NodeFromUiMessage {
                 client_id: 1234,
-                body: UiStartOrder {}.tmb(4321),
+                body: UiStartOrder { instance_opt: None }.tmb(4321),
             })
             .unwrap();
 
@@ -1257,13 +1664,13 @@ mod tests {
         let launcher = LauncherMock::new().launch_result(Err("booga".to_string()));
         let verifier_tools = VerifierToolsMock::new().process_is_running_result(true);
         let system = System::new("test");
-        let mut subject = Daemon::new(Box::new(launcher));
-        subject.params.insert(
+        let mut subject = Daemon::new(Box::new(launcher), RestartPolicy::default());
+        subject.instance_mut(DEFAULT_INSTANCE).params.insert(
             "db-password".to_string(),
             UiSetupResponseValue::new("db-password", "goober", Set),
         );
-        subject.node_ui_port = Some(1234);
-        subject.node_process_id = Some(3421);
+        subject.instance_mut(DEFAULT_INSTANCE).ui_port = Some(1234);
+        subject.instance_mut(DEFAULT_INSTANCE).process_id = Some(3421);
         subject.verifier_tools = Box::new(verifier_tools);
         let subject_addr = subject.start();
         subject_addr
@@ -1273,7 +1680,7 @@ mod tests {
         subject_addr
             .try_send(NodeFromUiMessage {
                 client_id: 1234,
-                body: UiStartOrder {}.tmb(4321),
+                body: UiStartOrder { instance_opt: None }.tmb(4321),
             })
             .unwrap();
 
@@ -1307,16 +1714,20 @@ mod tests {
                 redirect_ui_port: 7777,
             })));
         let verifier_tools = VerifierToolsMock::new();
-        let mut subject = Daemon::new(Box::new(launcher));
+        let mut subject = Daemon::new(Box::new(launcher), RestartPolicy::default());
         subject.ui_gateway_sub = Some(gateway_recipient.clone());
         subject.crash_notification_sub = Some(crash_notification_recipient);
         subject.verifier_tools = Box::new(verifier_tools);
-        subject.params = setup_cluster_from(vec![("data-directory", "bigglesworth", Set)]);
+        subject.instance_mut(DEFAULT_INSTANCE).params =
+            setup_cluster_from(vec![("data-directory", "bigglesworth", Set)]);
 
-        subject.handle_start_order(1234, 2345);
+        subject.handle_start_order(1234, 2345, DEFAULT_INSTANCE.to_string());
 
-        assert_eq!(subject.node_process_id, Some(54321));
-        assert_eq!(subject.node_ui_port, Some(7777));
+        assert_eq!(
+            subject.instance_mut(DEFAULT_INSTANCE).process_id,
+            Some(54321)
+        );
+        assert_eq!(subject.instance_mut(DEFAULT_INSTANCE).ui_port, Some(7777));
         let launch_params = launch_params_arc.lock().unwrap();
         assert_eq!(
             launch_params
@@ -1365,9 +1776,9 @@ mod tests {
         let verifier_tools = VerifierToolsMock::new()
             .process_is_running_params(&process_is_running_params_arc)
             .process_is_running_result(true);
-        let mut subject = Daemon::new(Box::new(LauncherMock::new()));
-        subject.node_ui_port = Some(7777);
-        subject.node_process_id = Some(8888);
+        let mut subject = Daemon::new(Box::new(LauncherMock::new()), RestartPolicy::default());
+        subject.instance_mut(DEFAULT_INSTANCE).ui_port = Some(7777);
+        subject.instance_mut(DEFAULT_INSTANCE).process_id = Some(8888);
         subject.verifier_tools = Box::new(verifier_tools);
         let subject_addr = subject.start();
         subject_addr
@@ -1413,9 +1824,9 @@ mod tests {
         let verifier_tools = VerifierToolsMock::new()
             .process_is_running_params(&process_is_running_params_arc)
             .process_is_running_result(false); // only consulted once; second time, we already know
-        let mut subject = Daemon::new(Box::new(LauncherMock::new()));
-        subject.node_ui_port = Some(7777);
-        subject.node_process_id = Some(8888);
+        let mut subject = Daemon::new(Box::new(LauncherMock::new()), RestartPolicy::default());
+        subject.instance_mut(DEFAULT_INSTANCE).ui_port = Some(7777);
+        subject.instance_mut(DEFAULT_INSTANCE).process_id = Some(8888);
         subject.verifier_tools = Box::new(verifier_tools);
         let subject_addr = subject.start();
         subject_addr
@@ -1446,9 +1857,9 @@ mod tests {
         let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
         let system = System::new("test");
         let verifier_tools = VerifierToolsMock::new();
-        let mut subject = Daemon::new(Box::new(LauncherMock::new()));
-        subject.node_ui_port = None;
-        subject.node_process_id = None;
+        let mut subject = Daemon::new(Box::new(LauncherMock::new()), RestartPolicy::default());
+        subject.instance_mut(DEFAULT_INSTANCE).ui_port = None;
+        subject.instance_mut(DEFAULT_INSTANCE).process_id = None;
         subject.verifier_tools = Box::new(verifier_tools);
         let subject_addr = subject.start();
         subject_addr
@@ -1490,9 +1901,9 @@ mod tests {
         let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
         let system = System::new("test");
         let verifier_tools = VerifierToolsMock::new();
-        let mut subject = Daemon::new(Box::new(LauncherMock::new()));
-        subject.node_ui_port = Some(1234);
-        subject.node_process_id = Some(12345);
+        let mut subject = Daemon::new(Box::new(LauncherMock::new()), RestartPolicy::default());
+        subject.instance_mut(DEFAULT_INSTANCE).ui_port = Some(1234);
+        subject.instance_mut(DEFAULT_INSTANCE).process_id = Some(54321);
         subject.verifier_tools = Box::new(verifier_tools);
         let subject_addr = subject.start();
         subject_addr
@@ -1522,6 +1933,10 @@ mod tests {
             &UiNodeCrashedBroadcast {
                 process_id: 54321,
                 crash_reason: CrashReason::Unrecognized("Standard error".to_string()),
+                exit_code_opt: Some(123),
+                last_log_lines: vec![],
+                restart_delay_secs_opt: None,
+                instance_opt: Some(DEFAULT_INSTANCE.to_string()),
             }
             .tmb(0)
         );
@@ -1551,11 +1966,11 @@ mod tests {
         let system = System::new("test");
         let ui_gateway_sub = ui_gateway.start().recipient();
         let verifier_tools = VerifierToolsMock::new();
-        let mut subject = Daemon::new(Box::new(LauncherMock::new()));
+        let mut subject = Daemon::new(Box::new(LauncherMock::new()), RestartPolicy::default());
         subject.ui_gateway_sub = Some(ui_gateway_sub);
         subject.verifier_tools = Box::new(verifier_tools);
-        subject.node_ui_port = None;
-        subject.node_process_id = None;
+        subject.instance_mut(DEFAULT_INSTANCE).ui_port = None;
+        subject.instance_mut(DEFAULT_INSTANCE).process_id = None;
 
         subject.handle_crash_notification(CrashNotification {
             process_id: 54321,
@@ -1568,4 +1983,637 @@ mod tests {
         let ui_gateway_recording = ui_gateway_recording_arc.lock().unwrap();
         assert_eq!(ui_gateway_recording.len(), 0);
     }
+
+    #[test]
+    fn setups_for_different_instances_are_kept_apart() {
+        let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
+        let get_modified_setup_params_arc = Arc::new(Mutex::new(vec![]));
+        let default_setup = setup_cluster_from(vec![("data-directory", "default-data", Set)]);
+        let second_setup = setup_cluster_from(vec![("data-directory", "second-data", Set)]);
+        let mut subject = Daemon::new(Box::new(LauncherMock::new()), RestartPolicy::default());
+        subject.instance_mut(DEFAULT_INSTANCE).params = default_setup.clone();
+        subject.setup_reporter = Box::new(
+            SetupReporterMock::new()
+                ._get_modified_setup_params(&get_modified_setup_params_arc)
+                .get_modified_setup_result(Ok(second_setup.clone())),
+        );
+        let system = System::new("test");
+        subject.ui_gateway_sub = Some(ui_gateway.start().recipient());
+
+        subject.handle_setup(
+            47,
+            74,
+            UiSetupRequest {
+                values: vec![UiSetupRequestValue::new("data-directory", "second-data")],
+                instance_opt: Some("second".to_string()),
+            },
+        );
+
+        System::current().stop();
+        system.run();
+        let get_modified_setup_params = get_modified_setup_params_arc.lock().unwrap();
+        assert_eq!(get_modified_setup_params[0].0, HashMap::new());
+        assert_eq!(subject.instance_mut("second").params, second_setup);
+        assert_eq!(subject.instance_mut(DEFAULT_INSTANCE).params, default_setup);
+        let ui_gateway_recording = ui_gateway_recording_arc.lock().unwrap();
+        assert_eq!(ui_gateway_recording.len(), 1);
+        assert_eq!(
+            ui_gateway_recording.get_record::<NodeToUiMessage>(0).body,
+            UiSetupResponse::new(
+                false,
+                second_setup,
+                ConfiguratorError::new(vec![]),
+                Some("second".to_string())
+            )
+            .tmb(74)
+        );
+    }
+
+    #[test]
+    fn start_order_for_an_instance_nobody_has_set_up_is_refused() {
+        let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
+        let system = System::new("test");
+        let mut subject = Daemon::new(Box::new(LauncherMock::new()), RestartPolicy::default()); // will panic if called
+        subject.ui_gateway_sub = Some(ui_gateway.start().recipient());
+
+        subject.handle_start_order(1234, 2345, "second".to_string());
+
+        System::current().stop();
+        system.run();
+        let ui_gateway_recording = ui_gateway_recording_arc.lock().unwrap();
+        assert_eq!(
+            ui_gateway_recording.get_record::<NodeToUiMessage>(0).body,
+            MessageBody {
+                opcode: "start".to_string(),
+                path: Conversation(2345),
+                payload: Err((
+                    NODE_LAUNCH_ERROR,
+                    "Could not launch Node: there is no instance named 'second'; set it up first"
+                        .to_string()
+                )),
+            }
+        );
+    }
+
+    #[test]
+    fn start_order_is_refused_when_a_running_instance_has_the_same_ui_port() {
+        let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
+        let system = System::new("test");
+        let verifier_tools = VerifierToolsMock::new().process_is_running_result(true);
+        let mut subject = Daemon::new(Box::new(LauncherMock::new()), RestartPolicy::default()); // will panic if called
+        subject.ui_gateway_sub = Some(ui_gateway.start().recipient());
+        subject.verifier_tools = Box::new(verifier_tools);
+        subject.instance_mut(DEFAULT_INSTANCE).params = setup_cluster_from(vec![
+            ("data-directory", "default-data", Set),
+            ("ui-port", "5333", Configured),
+        ]);
+        subject.instance_mut(DEFAULT_INSTANCE).process_id = Some(1111);
+        subject.instance_mut(DEFAULT_INSTANCE).ui_port = Some(5333);
+        subject.instance_mut("second").params = setup_cluster_from(vec![
+            ("data-directory", "second-data", Set),
+            ("ui-port", "5333", Configured),
+        ]);
+
+        subject.handle_start_order(1234, 2345, "second".to_string());
+
+        System::current().stop();
+        system.run();
+        let ui_gateway_recording = ui_gateway_recording_arc.lock().unwrap();
+        assert_eq!(
+            ui_gateway_recording.get_record::<NodeToUiMessage>(0).body,
+            MessageBody {
+                opcode: "start".to_string(),
+                path: Conversation(2345),
+                payload: Err((
+                    NODE_LAUNCH_ERROR,
+                    "Could not launch Node: instance 'default' is already running with ui-port 5333"
+                        .to_string()
+                )),
+            }
+        );
+    }
+
+    #[test]
+    fn instances_request_lists_every_instance() {
+        let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
+        let system = System::new("test");
+        let verifier_tools = VerifierToolsMock::new().process_is_running_result(true);
+        let mut subject = Daemon::new(Box::new(LauncherMock::new()), RestartPolicy::default());
+        subject.ui_gateway_sub = Some(ui_gateway.start().recipient());
+        subject.verifier_tools = Box::new(verifier_tools);
+        subject.instance_mut(DEFAULT_INSTANCE).process_id = Some(1111);
+        subject.instance_mut(DEFAULT_INSTANCE).ui_port = Some(5333);
+        subject.instance_mut("second").params =
+            setup_cluster_from(vec![("data-directory", "second-data", Set)]);
+
+        subject.handle_instances_request(1234, 2345);
+
+        System::current().stop();
+        system.run();
+        let ui_gateway_recording = ui_gateway_recording_arc.lock().unwrap();
+        let record = ui_gateway_recording.get_record::<NodeToUiMessage>(0);
+        assert_eq!(record.target, ClientId(1234));
+        assert_eq!(
+            record.body,
+            UiInstancesResponse {
+                instances: vec![
+                    UiInstance {
+                        name: "default".to_string(),
+                        running: true,
+                        process_id_opt: Some(1111),
+                        ui_port_opt: Some(5333),
+                    },
+                    UiInstance {
+                        name: "second".to_string(),
+                        running: false,
+                        process_id_opt: None,
+                        ui_port_opt: None,
+                    },
+                ]
+            }
+            .tmb(2345)
+        );
+    }
+
+    #[test]
+    fn configuration_schema_request_is_answered_from_the_shared_schema() {
+        let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
+        let system = System::new("test");
+        let subject = Daemon::new(Box::new(LauncherMock::new()), RestartPolicy::default());
+        let subject_addr = subject.start();
+        subject_addr
+            .try_send(make_bind_message(ui_gateway))
+            .unwrap();
+
+        subject_addr
+            .try_send(NodeFromUiMessage {
+                client_id: 1234,
+                body: UiConfigurationSchemaRequest {}.tmb(2345),
+            })
+            .unwrap();
+
+        System::current().stop();
+        system.run();
+        let ui_gateway_recording = ui_gateway_recording_arc.lock().unwrap();
+        let record = ui_gateway_recording.get_record::<NodeToUiMessage>(0);
+        assert_eq!(record.target, ClientId(1234));
+        assert_eq!(
+            record.body,
+            UiConfigurationSchemaResponse {
+                parameters: SetupReporterReal::get_configuration_schema()
+            }
+            .tmb(2345)
+        );
+    }
+
+    #[test]
+    fn dns_status_request_reports_nameservers_and_who_manages_them() {
+        let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
+        let system = System::new("test");
+        let dns_modifier = DnsModifierMock::new()
+            .inspect_result(Ok("127.0.0.1\n".to_string()))
+            .manager_result(Some("systemd-resolved".to_string()));
+        let mut subject = Daemon::new(Box::new(LauncherMock::new()), RestartPolicy::default());
+        subject.ui_gateway_sub = Some(ui_gateway.start().recipient());
+        subject.dns_modifier_factory =
+            Box::new(DnsModifierFactoryMock::new().make_result(Some(Box::new(dns_modifier))));
+
+        subject.handle_dns_status(1234, 2345);
+
+        System::current().stop();
+        system.run();
+        let ui_gateway_recording = ui_gateway_recording_arc.lock().unwrap();
+        let record = ui_gateway_recording.get_record::<NodeToUiMessage>(0);
+        assert_eq!(record.target, ClientId(1234));
+        assert_eq!(
+            record.body,
+            UiDnsStatusResponse {
+                status: "subverted".to_string(),
+                nameservers: vec!["127.0.0.1".to_string()],
+                managed_by_opt: Some("systemd-resolved".to_string()),
+            }
+            .tmb(2345)
+        );
+    }
+
+    #[test]
+    fn dns_status_request_reports_a_system_it_does_not_understand() {
+        let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
+        let system = System::new("test");
+        let mut subject = Daemon::new(Box::new(LauncherMock::new()), RestartPolicy::default());
+        subject.ui_gateway_sub = Some(ui_gateway.start().recipient());
+        subject.dns_modifier_factory = Box::new(DnsModifierFactoryMock::new().make_result(None));
+
+        subject.handle_dns_status(1234, 2345);
+
+        System::current().stop();
+        system.run();
+        let ui_gateway_recording = ui_gateway_recording_arc.lock().unwrap();
+        let record = ui_gateway_recording.get_record::<NodeToUiMessage>(0);
+        assert_eq!(
+            record.body,
+            MessageBody {
+                opcode: "dnsStatus".to_string(),
+                path: Conversation(2345),
+                payload: Err((
+                    DNS_ERROR,
+                    "Cannot read DNS settings: Don't know how to modify DNS settings on this system"
+                        .to_string()
+                )),
+            }
+        );
+    }
+
+    #[test]
+    fn dns_subvert_request_subverts_and_warns_about_a_manager() {
+        init_test_logging();
+        let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
+        let system = System::new("test");
+        let subvert_params_arc = Arc::new(Mutex::new(vec![]));
+        let dns_modifier = DnsModifierMock::new()
+            .manager_result(Some("NetworkManager".to_string()))
+            .subvert_params(&subvert_params_arc)
+            .subvert_result(Ok(()));
+        let mut subject = Daemon::new(Box::new(LauncherMock::new()), RestartPolicy::default());
+        subject.ui_gateway_sub = Some(ui_gateway.start().recipient());
+        subject.dns_modifier_factory =
+            Box::new(DnsModifierFactoryMock::new().make_result(Some(Box::new(dns_modifier))));
+
+        subject.handle_dns_subvert(1234, 2345);
+
+        System::current().stop();
+        system.run();
+        assert_eq!(subvert_params_arc.lock().unwrap().len(), 1);
+        let ui_gateway_recording = ui_gateway_recording_arc.lock().unwrap();
+        let record = ui_gateway_recording.get_record::<NodeToUiMessage>(0);
+        assert_eq!(record.body, UiDnsSubvertResponse {}.tmb(2345));
+        TestLogHandler::new().exists_log_containing(
+            "WARN: Daemon: Subverting DNS settings managed by NetworkManager, which may undo it",
+        );
+    }
+
+    #[test]
+    fn dns_revert_request_reports_failure() {
+        let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
+        let system = System::new("test");
+        let revert_params_arc = Arc::new(Mutex::new(vec![]));
+        let dns_modifier = DnsModifierMock::new()
+            .revert_params(&revert_params_arc)
+            .revert_result(Err(
+                "There do not appear to be any DNS settings to revert to".to_string(),
+            ));
+        let mut subject = Daemon::new(Box::new(LauncherMock::new()), RestartPolicy::default());
+        subject.ui_gateway_sub = Some(ui_gateway.start().recipient());
+        subject.dns_modifier_factory =
+            Box::new(DnsModifierFactoryMock::new().make_result(Some(Box::new(dns_modifier))));
+
+        subject.handle_dns_revert(1234, 2345);
+
+        System::current().stop();
+        system.run();
+        assert_eq!(revert_params_arc.lock().unwrap().len(), 1);
+        let ui_gateway_recording = ui_gateway_recording_arc.lock().unwrap();
+        let record = ui_gateway_recording.get_record::<NodeToUiMessage>(0);
+        assert_eq!(
+            record.body,
+            MessageBody {
+                opcode: "dnsRevert".to_string(),
+                path: Conversation(2345),
+                payload: Err((
+                    DNS_ERROR,
+                    "Cannot revert DNS settings: There do not appear to be any DNS settings to revert to"
+                        .to_string()
+                )),
+            }
+        );
+    }
+
+    #[test]
+    fn messages_for_a_node_are_redirected_to_the_instance_they_name() {
+        let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
+        let system = System::new("test");
+        let process_is_running_params_arc = Arc::new(Mutex::new(vec![]));
+        let verifier_tools = VerifierToolsMock::new()
+            .process_is_running_params(&process_is_running_params_arc)
+            .process_is_running_result(true);
+        let mut subject = Daemon::new(Box::new(LauncherMock::new()), RestartPolicy::default());
+        subject.ui_gateway_sub = Some(ui_gateway.start().recipient());
+        subject.verifier_tools = Box::new(verifier_tools);
+        subject.instance_mut(DEFAULT_INSTANCE).process_id = Some(1111);
+        subject.instance_mut(DEFAULT_INSTANCE).ui_port = Some(5333);
+        subject.instance_mut("second").process_id = Some(2222);
+        subject.instance_mut("second").ui_port = Some(5334);
+        let body = MessageBody {
+            opcode: "shutdown".to_string(),
+            path: Conversation(4321),
+            payload: Ok(r#"{"instance":"second"}"#.to_string()),
+        };
+
+        subject.handle_unexpected_message(1234, body);
+
+        System::current().stop();
+        system.run();
+        let ui_gateway_recording = ui_gateway_recording_arc.lock().unwrap();
+        let record = ui_gateway_recording
+            .get_record::<NodeToUiMessage>(0)
+            .clone();
+        let (payload, _) = UiRedirect::fmb(record.body).unwrap();
+        assert_eq!(
+            payload,
+            UiRedirect {
+                port: 5334,
+                opcode: "shutdown".to_string(),
+                context_id: Some(4321),
+                payload: r#"{"instance":"second"}"#.to_string(),
+            }
+        );
+        let process_is_running_params = process_is_running_params_arc.lock().unwrap();
+        assert_eq!(*process_is_running_params, vec![2222]);
+    }
+
+    #[test]
+    fn crash_notification_names_the_instance_that_crashed_and_leaves_the_others_alone() {
+        let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
+        let system = System::new("test");
+        let mut subject = Daemon::new(Box::new(LauncherMock::new()), RestartPolicy::default());
+        subject.ui_gateway_sub = Some(ui_gateway.start().recipient());
+        subject.instance_mut(DEFAULT_INSTANCE).process_id = Some(1111);
+        subject.instance_mut(DEFAULT_INSTANCE).ui_port = Some(5333);
+        subject.instance_mut("second").process_id = Some(2222);
+        subject.instance_mut("second").ui_port = Some(5334);
+
+        subject.handle_crash_notification(CrashNotification {
+            process_id: 2222,
+            exit_code: Some(123),
+            stderr: Some("Standard error".to_string()),
+        });
+
+        System::current().stop();
+        system.run();
+        assert_eq!(subject.instance_mut("second").process_id, None);
+        assert_eq!(subject.instance_mut("second").ui_port, None);
+        assert_eq!(
+            subject.instance_mut(DEFAULT_INSTANCE).process_id,
+            Some(1111)
+        );
+        assert_eq!(subject.instance_mut(DEFAULT_INSTANCE).ui_port, Some(5333));
+        let ui_gateway_recording = ui_gateway_recording_arc.lock().unwrap();
+        let record = ui_gateway_recording.get_record::<NodeToUiMessage>(0);
+        assert_eq!(record.target, MessageTarget::AllClients);
+        assert_eq!(
+            record.body,
+            UiNodeCrashedBroadcast {
+                process_id: 2222,
+                crash_reason: CrashReason::Unrecognized("Standard error".to_string()),
+                exit_code_opt: Some(123),
+                last_log_lines: vec![],
+                restart_delay_secs_opt: None,
+                instance_opt: Some("second".to_string()),
+            }
+            .tmb(0)
+        );
+    }
+
+    #[test]
+    fn crash_notification_reports_the_end_of_the_log_and_schedules_a_restart() {
+        let data_dir = ensure_node_home_directory_exists(
+            "daemon",
+            "crash_notification_reports_the_end_of_the_log_and_schedules_a_restart",
+        );
+        std::fs::write(
+            data_dir.join(CURRENT_LOGFILE_NAME),
+            "First line\nSecond line\nLast line\n",
+        )
+        .unwrap();
+        let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
+        let system = System::new("test");
+        let mut subject = Daemon::new(
+            Box::new(LauncherMock::new()),
+            RestartPolicy {
+                max_retries: 2,
+                backoff: Duration::from_secs(5),
+            },
+        );
+        subject.ui_gateway_sub = Some(ui_gateway.start().recipient());
+        subject.instance_mut(DEFAULT_INSTANCE).params =
+            setup_cluster_from(vec![("data-directory", data_dir.to_str().unwrap(), Set)]);
+        subject.instance_mut(DEFAULT_INSTANCE).process_id = Some(1111);
+        subject.instance_mut(DEFAULT_INSTANCE).ui_port = Some(5333);
+
+        let result = subject.handle_crash_notification(CrashNotification {
+            process_id: 1111,
+            exit_code: Some(101),
+            stderr: None,
+        });
+
+        System::current().stop();
+        system.run();
+        assert_eq!(
+            result,
+            Some((DEFAULT_INSTANCE.to_string(), Duration::from_secs(5)))
+        );
+        assert_eq!(subject.instance_mut(DEFAULT_INSTANCE).retries, 1);
+        let ui_gateway_recording = ui_gateway_recording_arc.lock().unwrap();
+        assert_eq!(
+            ui_gateway_recording.get_record::<NodeToUiMessage>(0).body,
+            UiNodeCrashedBroadcast {
+                process_id: 1111,
+                crash_reason: CrashReason::Unrecognized(String::new()),
+                exit_code_opt: Some(101),
+                last_log_lines: vec![
+                    "First line".to_string(),
+                    "Second line".to_string(),
+                    "Last line".to_string()
+                ],
+                restart_delay_secs_opt: Some(5),
+                instance_opt: Some(DEFAULT_INSTANCE.to_string()),
+            }
+            .tmb(0)
+        );
+    }
+
+    #[test]
+    fn crash_notification_schedules_no_restart_once_the_retries_run_out() {
+        let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
+        let system = System::new("test");
+        let mut subject = Daemon::new(
+            Box::new(LauncherMock::new()),
+            RestartPolicy {
+                max_retries: 2,
+                backoff: Duration::from_secs(5),
+            },
+        );
+        subject.ui_gateway_sub = Some(ui_gateway.start().recipient());
+        subject.instance_mut(DEFAULT_INSTANCE).process_id = Some(1111);
+        subject.instance_mut(DEFAULT_INSTANCE).ui_port = Some(5333);
+        subject.instance_mut(DEFAULT_INSTANCE).retries = 2;
+
+        let result = subject.handle_crash_notification(CrashNotification {
+            process_id: 1111,
+            exit_code: Some(101),
+            stderr: None,
+        });
+
+        System::current().stop();
+        system.run();
+        assert_eq!(result, None);
+        let ui_gateway_recording = ui_gateway_recording_arc.lock().unwrap();
+        let (broadcast, _) = UiNodeCrashedBroadcast::fmb(
+            ui_gateway_recording
+                .get_record::<NodeToUiMessage>(0)
+                .body
+                .clone(),
+        )
+        .unwrap();
+        assert_eq!(broadcast.restart_delay_secs_opt, None);
+        assert_eq!(broadcast.last_log_lines, Vec::<String>::new());
+    }
+
+    #[test]
+    fn node_that_shuts_down_cleanly_is_not_restarted() {
+        let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
+        let system = System::new("test");
+        let mut subject = Daemon::new(
+            Box::new(LauncherMock::new()),
+            RestartPolicy {
+                max_retries: 2,
+                backoff: Duration::from_secs(5),
+            },
+        );
+        subject.ui_gateway_sub = Some(ui_gateway.start().recipient());
+        subject.instance_mut(DEFAULT_INSTANCE).process_id = Some(1111);
+        subject.instance_mut(DEFAULT_INSTANCE).ui_port = Some(5333);
+
+        let result = subject.handle_crash_notification(CrashNotification {
+            process_id: 1111,
+            exit_code: Some(0),
+            stderr: None,
+        });
+
+        System::current().stop();
+        system.run();
+        assert_eq!(result, None);
+        assert_eq!(subject.instance_mut(DEFAULT_INSTANCE).retries, 0);
+        let ui_gateway_recording = ui_gateway_recording_arc.lock().unwrap();
+        let (broadcast, _) = UiNodeCrashedBroadcast::fmb(
+            ui_gateway_recording
+                .get_record::<NodeToUiMessage>(0)
+                .body
+                .clone(),
+        )
+        .unwrap();
+        assert_eq!(broadcast.restart_delay_secs_opt, None);
+    }
+
+    #[test]
+    fn crash_after_a_stable_run_earns_back_the_retries() {
+        let (ui_gateway, _, _) = make_recorder();
+        let system = System::new("test");
+        let mut subject = Daemon::new(
+            Box::new(LauncherMock::new()),
+            RestartPolicy {
+                max_retries: 2,
+                backoff: Duration::from_secs(5),
+            },
+        );
+        subject.ui_gateway_sub = Some(ui_gateway.start().recipient());
+        subject.instance_mut(DEFAULT_INSTANCE).process_id = Some(1111);
+        subject.instance_mut(DEFAULT_INSTANCE).ui_port = Some(5333);
+        subject.instance_mut(DEFAULT_INSTANCE).retries = 2;
+        subject.instance_mut(DEFAULT_INSTANCE).started_at = Some(Instant::now() - STABLE_RUN_TIME);
+
+        let result = subject.handle_crash_notification(CrashNotification {
+            process_id: 1111,
+            exit_code: Some(101),
+            stderr: None,
+        });
+
+        System::current().stop();
+        system.run();
+        assert_eq!(
+            result,
+            Some((DEFAULT_INSTANCE.to_string(), Duration::from_secs(5)))
+        );
+        assert_eq!(subject.instance_mut(DEFAULT_INSTANCE).retries, 1);
+    }
+
+    #[test]
+    fn restart_relaunches_the_node_and_broadcasts_the_news() {
+        let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
+        let (daemon, _, _) = make_recorder();
+        let system = System::new("test");
+        let launch_params_arc = Arc::new(Mutex::new(vec![]));
+        let launcher = LauncherMock::new()
+            .launch_params(&launch_params_arc)
+            .launch_result(Ok(Some(LaunchSuccess {
+                new_process_id: 2222,
+                redirect_ui_port: 5334,
+            })));
+        let mut subject = Daemon::new(Box::new(launcher), RestartPolicy::default());
+        subject.ui_gateway_sub = Some(ui_gateway.start().recipient());
+        subject.crash_notification_sub = Some(daemon.start().recipient());
+        subject.instance_mut(DEFAULT_INSTANCE).params =
+            setup_cluster_from(vec![("data-directory", "bigglesworth", Set)]);
+        subject.instance_mut(DEFAULT_INSTANCE).retries = 1;
+
+        let result = subject.handle_restart(DEFAULT_INSTANCE);
+
+        System::current().stop();
+        system.run();
+        assert_eq!(result, None);
+        assert_eq!(
+            subject.instance_mut(DEFAULT_INSTANCE).process_id,
+            Some(2222)
+        );
+        assert_eq!(subject.instance_mut(DEFAULT_INSTANCE).ui_port, Some(5334));
+        let launch_params = launch_params_arc.lock().unwrap();
+        assert_eq!(
+            launch_params[0].0,
+            HashMap::from_iter(vec![(
+                "data-directory".to_string(),
+                "bigglesworth".to_string()
+            )])
+        );
+        let ui_gateway_recording = ui_gateway_recording_arc.lock().unwrap();
+        let record = ui_gateway_recording.get_record::<NodeToUiMessage>(0);
+        assert_eq!(record.target, MessageTarget::AllClients);
+        assert_eq!(
+            record.body,
+            UiNodeRestartedBroadcast {
+                new_process_id: 2222,
+                redirect_ui_port: 5334,
+                attempt: 1,
+                instance_opt: Some(DEFAULT_INSTANCE.to_string()),
+            }
+            .tmb(0)
+        );
+    }
+
+    #[test]
+    fn failed_restart_is_tried_again_later_if_the_policy_allows() {
+        let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
+        let (daemon, _, _) = make_recorder();
+        let system = System::new("test");
+        let launcher = LauncherMock::new().launch_result(Err("Booga".to_string()));
+        let mut subject = Daemon::new(
+            Box::new(launcher),
+            RestartPolicy {
+                max_retries: 3,
+                backoff: Duration::from_secs(5),
+            },
+        );
+        subject.ui_gateway_sub = Some(ui_gateway.start().recipient());
+        subject.crash_notification_sub = Some(daemon.start().recipient());
+        subject.instance_mut(DEFAULT_INSTANCE).retries = 1;
+
+        let result = subject.handle_restart(DEFAULT_INSTANCE);
+
+        System::current().stop();
+        system.run();
+        assert_eq!(result, Some(Duration::from_secs(10)));
+        assert_eq!(subject.instance_mut(DEFAULT_INSTANCE).retries, 2);
+        assert_eq!(subject.instance_mut(DEFAULT_INSTANCE).process_id, None);
+        let ui_gateway_recording = ui_gateway_recording_arc.lock().unwrap();
+        assert_eq!(ui_gateway_recording.len(), 0);
+    }
 }
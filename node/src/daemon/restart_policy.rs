@@ -0,0 +1,75 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai). All rights reserved.
+
+use std::time::Duration;
+
+pub const DEFAULT_RESTART_BACKOFF: Duration = Duration::from_secs(5);
+const MAX_RESTART_BACKOFF: Duration = Duration::from_secs(600);
+
+// A Node that's stayed up this long has earned back its full set of retries
+pub const STABLE_RUN_TIME: Duration = Duration::from_secs(600);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RestartPolicy {
+    pub max_retries: u32,
+    pub backoff: Duration,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        RestartPolicy {
+            max_retries: 0,
+            backoff: DEFAULT_RESTART_BACKOFF,
+        }
+    }
+}
+
+impl RestartPolicy {
+    // Retries count from 1. The wait doubles each time, up to a limit; None means give up.
+    pub fn delay_before_retry(&self, retry: u32) -> Option<Duration> {
+        if retry == 0 || retry > self.max_retries {
+            return None;
+        }
+        let factor = 1u32.checked_shl(retry - 1).unwrap_or(u32::MAX);
+        Some(
+            self.backoff
+                .checked_mul(factor)
+                .map_or(MAX_RESTART_BACKOFF, |delay| delay.min(MAX_RESTART_BACKOFF)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_policy_never_restarts() {
+        let subject = RestartPolicy::default();
+
+        assert_eq!(subject.delay_before_retry(1), None);
+    }
+
+    #[test]
+    fn delay_doubles_until_the_retries_run_out() {
+        let subject = RestartPolicy {
+            max_retries: 3,
+            backoff: Duration::from_secs(5),
+        };
+
+        assert_eq!(subject.delay_before_retry(1), Some(Duration::from_secs(5)));
+        assert_eq!(subject.delay_before_retry(2), Some(Duration::from_secs(10)));
+        assert_eq!(subject.delay_before_retry(3), Some(Duration::from_secs(20)));
+        assert_eq!(subject.delay_before_retry(4), None);
+    }
+
+    #[test]
+    fn delay_never_exceeds_the_limit() {
+        let subject = RestartPolicy {
+            max_retries: 100,
+            backoff: Duration::from_secs(5),
+        };
+
+        assert_eq!(subject.delay_before_retry(8), Some(MAX_RESTART_BACKOFF));
+        assert_eq!(subject.delay_before_retry(100), Some(MAX_RESTART_BACKOFF));
+    }
+}
@@ -16,22 +16,28 @@ use crate::sub_lib::accountant::DEFAULT_EARNING_WALLET;
 use crate::sub_lib::neighborhood::NodeDescriptor;
 use crate::sub_lib::utils::make_new_multi_config;
 use crate::test_utils::main_cryptde;
-use clap::value_t;
+use clap::{value_t, ArgSettings};
 use itertools::Itertools;
 use masq_lib::command::StdStreams;
 use masq_lib::constants::DEFAULT_CHAIN_NAME;
 use masq_lib::messages::UiSetupResponseValueStatus::{Blank, Configured, Default, Required, Set};
-use masq_lib::messages::{UiSetupRequestValue, UiSetupResponseValue, UiSetupResponseValueStatus};
+use masq_lib::messages::{
+    UiConfigurationParameter, UiSetupRequestValue, UiSetupResponseValue, UiSetupResponseValueStatus,
+};
 use masq_lib::multi_config::{
     CommandLineVcl, ConfigFileVcl, EnvironmentVcl, MultiConfig, VirtualCommandLine,
 };
-use masq_lib::shared_schema::{shared_app, ConfiguratorError};
+use masq_lib::shared_schema::{
+    shared_app, ConfiguratorError, ParamError, ParamErrorCode, INTEGER_PARAMETERS,
+};
 use masq_lib::test_utils::fake_stream_holder::{ByteArrayReader, ByteArrayWriter};
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::str::FromStr;
 
 const CONSOLE_DIAGNOSTICS: bool = false;
+// Parameters a running Node can be told to change through some other UI message
+const LIVE_PARAMETERS: &[&str] = &["log-level"];
 
 pub type SetupCluster = HashMap<String, UiSetupResponseValue>;
 
@@ -106,12 +112,18 @@ impl SetupReporter for SetupReporterReal {
         });
         let data_directory = match all_but_configured.get("data-directory") {
             Some(uisrv) if uisrv.status == Set => PathBuf::from(&uisrv.value),
-            _ => data_directory_from_context(
+            _ => match data_directory_from_context(
                 self.dirs_wrapper.as_ref(),
                 &real_user,
                 &data_directory_opt,
                 &chain_name,
-            ),
+            ) {
+                Ok(data_directory) => data_directory,
+                Err(error) => {
+                    error_so_far.extend(error);
+                    PathBuf::new()
+                }
+            },
         };
         let (configured_setup, error_opt) = Self::calculate_configured_setup(
             self.dirs_wrapper.as_ref(),
@@ -209,6 +221,50 @@ impl SetupReporterReal {
             .collect()
     }
 
+    // Hidden parameters are for developers, and a settings form shouldn't offer them
+    pub fn get_configuration_schema() -> Vec<UiConfigurationParameter> {
+        let schema = shared_app(app_head());
+        schema
+            .p
+            .opts
+            .iter()
+            .filter(|opt| !opt.b.is_set(ArgSettings::Hidden))
+            .map(|opt| {
+                let name = opt.b.name;
+                let possible_values = opt
+                    .v
+                    .possible_vals
+                    .as_ref()
+                    .map(|values| values.iter().map(|v| v.to_string()).collect())
+                    .unwrap_or_default();
+                let range_opt = INTEGER_PARAMETERS
+                    .iter()
+                    .find(|(integer_name, _, _)| *integer_name == name)
+                    .map(|(_, minimum, maximum)| (*minimum, *maximum));
+                let parameter_type = match (&opt.v.possible_vals, range_opt) {
+                    (Some(_), _) => "choice",
+                    (None, Some(_)) => "integer",
+                    (None, None) => "string",
+                };
+                UiConfigurationParameter {
+                    name: name.to_string(),
+                    parameter_type: parameter_type.to_string(),
+                    help: opt.b.help.unwrap_or("").to_string(),
+                    possible_values,
+                    minimum_opt: range_opt.map(|(minimum, _)| minimum),
+                    maximum_opt: range_opt.map(|(_, maximum)| maximum),
+                    default_opt: opt
+                        .v
+                        .default_val
+                        .and_then(|os_str| os_str.to_str())
+                        .map(|value| value.to_string()),
+                    restart_required: !LIVE_PARAMETERS.contains(&name),
+                }
+            })
+            .sorted_by(|a, b| a.name.cmp(&b.name))
+            .collect()
+    }
+
     fn real_user_from_str(s: &str) -> Option<crate::bootstrapper::RealUser> {
         match crate::bootstrapper::RealUser::from_str(s) {
             Ok(ru) => Some(ru),
@@ -285,6 +341,9 @@ impl SetupReporterReal {
         if let Some(error) = error_opt {
             error_so_far.extend(error);
         }
+        if let Some(error) = Self::check_db_password(combined_setup, &persistent_config_opt) {
+            error_so_far.extend(error);
+        }
         let mut setup = value_retrievers(dirs_wrapper)
             .into_iter()
             .map(|r| {
@@ -313,6 +372,35 @@ impl SetupReporterReal {
         }
     }
 
+    // Without the password, a Node can't use the consuming wallet the database says it has, and
+    // would quietly run without one
+    fn check_db_password(
+        combined_setup: &SetupCluster,
+        persistent_config_opt: &Option<Box<dyn PersistentConfiguration>>,
+    ) -> Option<ConfiguratorError> {
+        let is_blank = |name: &str| match combined_setup.get(name) {
+            Some(uisrv) => uisrv.value.is_empty(),
+            None => true,
+        };
+        if !is_required_for_blockchain(combined_setup)
+            || !is_blank("db-password")
+            || !is_blank("consuming-private-key")
+        {
+            return None;
+        }
+        match persistent_config_opt
+            .as_ref()
+            .map(|pc| pc.consuming_wallet_derivation_path())
+        {
+            Some(Ok(Some(_))) => Some(ConfiguratorError::new(vec![ParamError::with_code(
+                "db-password",
+                ParamErrorCode::Required,
+                "Database holds a consuming wallet derivation path, which can't be used without --db-password",
+            )])),
+            _ => None,
+        }
+    }
+
     fn combine_clusters(clusters: Vec<&SetupCluster>) -> SetupCluster {
         let mut result: SetupCluster = HashMap::new();
         clusters.into_iter().for_each(|cluster| {
@@ -497,6 +585,13 @@ fn is_required_for_blockchain(params: &SetupCluster) -> bool {
     !matches! (params.get("neighborhood-mode"), Some(nhm) if &nhm.value == "zero-hop")
 }
 
+struct AdditionalClandestinePorts {}
+impl ValueRetriever for AdditionalClandestinePorts {
+    fn value_name(&self) -> &'static str {
+        "additional-clandestine-ports"
+    }
+}
+
 struct BlockchainServiceUrl {}
 impl ValueRetriever for BlockchainServiceUrl {
     fn value_name(&self) -> &'static str {
@@ -595,17 +690,14 @@ impl ValueRetriever for DataDirectory {
         let real_user = &bootstrapper_config.real_user;
         let chain_name = chain_name_from_id(bootstrapper_config.blockchain_bridge_config.chain_id);
         let data_directory_opt = None;
-        Some((
-            data_directory_from_context(
-                self.dirs_wrapper.as_ref(),
-                &real_user,
-                &data_directory_opt,
-                chain_name,
-            )
-            .to_string_lossy()
-            .to_string(),
-            Default,
-        ))
+        let data_directory = data_directory_from_context(
+            self.dirs_wrapper.as_ref(),
+            &real_user,
+            &data_directory_opt,
+            chain_name,
+        )
+        .ok()?;
+        Some((data_directory.to_string_lossy().to_string(), Default))
     }
 
     fn is_required(&self, _params: &SetupCluster) -> bool {
@@ -681,6 +773,20 @@ impl ValueRetriever for EarningWallet {
     }
 }
 
+struct EncryptedSni {}
+impl ValueRetriever for EncryptedSni {
+    fn value_name(&self) -> &'static str {
+        "encrypted-sni"
+    }
+}
+
+struct ExitPolicy {}
+impl ValueRetriever for ExitPolicy {
+    fn value_name(&self) -> &'static str {
+        "exit-policy"
+    }
+}
+
 struct GasPrice {}
 impl ValueRetriever for GasPrice {
     fn value_name(&self) -> &'static str {
@@ -742,6 +848,13 @@ impl ValueRetriever for LogLevel {
     }
 }
 
+struct Multipath {}
+impl ValueRetriever for Multipath {
+    fn value_name(&self) -> &'static str {
+        "multipath"
+    }
+}
+
 struct NeighborhoodMode {}
 impl ValueRetriever for NeighborhoodMode {
     fn value_name(&self) -> &'static str {
@@ -843,8 +956,37 @@ impl RealUser {
     }
 }
 
+struct SecureDnsServers {}
+impl ValueRetriever for SecureDnsServers {
+    fn value_name(&self) -> &'static str {
+        "secure-dns-servers"
+    }
+}
+
+struct SocksCredentials {}
+impl ValueRetriever for SocksCredentials {
+    fn value_name(&self) -> &'static str {
+        "socks-credentials"
+    }
+}
+
+struct SocksPort {}
+impl ValueRetriever for SocksPort {
+    fn value_name(&self) -> &'static str {
+        "socks-port"
+    }
+}
+
+struct StreamIdleTimeout {}
+impl ValueRetriever for StreamIdleTimeout {
+    fn value_name(&self) -> &'static str {
+        "stream-idle-timeout"
+    }
+}
+
 fn value_retrievers(dirs_wrapper: &dyn DirsWrapper) -> Vec<Box<dyn ValueRetriever>> {
     vec![
+        Box::new(AdditionalClandestinePorts {}),
         Box::new(BlockchainServiceUrl {}),
         Box::new(Chain {}),
         Box::new(ClandestinePort {}),
@@ -855,13 +997,20 @@ fn value_retrievers(dirs_wrapper: &dyn DirsWrapper) -> Vec<Box<dyn ValueRetrieve
         Box::new(DbPassword {}),
         Box::new(DnsServers {}),
         Box::new(EarningWallet {}),
+        Box::new(EncryptedSni {}),
+        Box::new(ExitPolicy {}),
         Box::new(GasPrice {}),
         Box::new(Ip {}),
         Box::new(LogLevel {}),
+        Box::new(Multipath {}),
         Box::new(NeighborhoodMode {}),
         Box::new(Neighbors {}),
         #[cfg(not(target_os = "windows"))]
         Box::new(RealUser::new(dirs_wrapper)),
+        Box::new(SecureDnsServers {}),
+        Box::new(SocksCredentials {}),
+        Box::new(SocksPort {}),
+        Box::new(StreamIdleTimeout {}),
     ]
 }
 
@@ -903,6 +1052,46 @@ mod tests {
         });
     }
 
+    #[test]
+    fn configuration_schema_describes_each_kind_of_parameter() {
+        let result = SetupReporterReal::get_configuration_schema();
+
+        let find = |name: &str| {
+            result
+                .iter()
+                .find(|parameter| parameter.name == name)
+                .unwrap_or_else(|| panic!("{} is missing", name))
+                .clone()
+        };
+        let chain = find("chain");
+        assert_eq!(chain.parameter_type, "choice".to_string());
+        assert_eq!(chain.default_opt, None);
+        assert_eq!(chain.possible_values.contains(&"mainnet".to_string()), true);
+        assert_eq!(chain.restart_required, true);
+        let clandestine_port = find("clandestine-port");
+        assert_eq!(clandestine_port.parameter_type, "integer".to_string());
+        assert_eq!(clandestine_port.minimum_opt, Some(1025));
+        assert_eq!(clandestine_port.maximum_opt, Some(65535));
+        assert_eq!(clandestine_port.possible_values, Vec::<String>::new());
+        let neighbors = find("neighbors");
+        assert_eq!(neighbors.parameter_type, "string".to_string());
+        assert_eq!(neighbors.minimum_opt, None);
+        assert_eq!(neighbors.help.is_empty(), false);
+        assert_eq!(
+            find("config-file").default_opt,
+            Some("config.toml".to_string())
+        );
+        assert_eq!(find("log-level").restart_required, false);
+        assert_eq!(
+            result
+                .iter()
+                .any(|parameter| parameter.name == "crash-point"),
+            false
+        );
+        let names = result.iter().map(|p| p.name.clone()).collect_vec();
+        assert_eq!(names, names.iter().cloned().sorted().collect_vec());
+    }
+
     #[test]
     fn some_items_are_censored_from_defaults() {
         let result = SetupReporterReal::get_default_params();
@@ -971,6 +1160,7 @@ mod tests {
             .unwrap();
 
         let expected_result = vec![
+            ("additional-clandestine-ports", "", Blank),
             ("blockchain-service-url", "", Required),
             ("chain", DEFAULT_CHAIN_NAME, Default),
             ("clandestine-port", "1234", Default),
@@ -985,9 +1175,12 @@ mod tests {
                 "0x0000000000000000000000000000000000000000",
                 Configured,
             ),
+            ("encrypted-sni", "", Blank),
+            ("exit-policy", "", Blank),
             ("gas-price", "1234567890", Default),
             ("ip", "4.3.2.1", Set),
             ("log-level", "warn", Default),
+            ("multipath", "", Blank),
             ("neighborhood-mode", "standard", Default),
             (
                 "neighbors",
@@ -1002,6 +1195,10 @@ mod tests {
                     .to_string(),
                 Default,
             ),
+            ("secure-dns-servers", "", Blank),
+            ("socks-credentials", "", Blank),
+            ("socks-port", "", Blank),
+            ("stream-idle-timeout", "", Blank),
         ]
         .into_iter()
         .map(|(name, value, status)| {
@@ -1026,6 +1223,7 @@ mod tests {
             "get_modified_setup_database_nonexistent_everything_preexistent",
         );
         let existing_setup = setup_cluster_from(vec![
+            ("additional-clandestine-ports", "8443", Set),
             ("blockchain-service-url", "https://example.com", Set),
             ("chain", TEST_DEFAULT_CHAIN_NAME, Set),
             ("clandestine-port", "1234", Set),
@@ -1035,19 +1233,27 @@ mod tests {
             ("db-password", "password", Set),
             ("dns-servers", "8.8.8.8", Set),
             ("earning-wallet", "0x0123456789012345678901234567890123456789", Set),
+            ("encrypted-sni", "prompt", Set),
+            ("exit-policy", "port:25", Set),
             ("gas-price", "50", Set),
             ("ip", "4.3.2.1", Set),
             ("log-level", "error", Set),
+            ("multipath", "stripe", Set),
             ("neighborhood-mode", "originate-only", Set),
             ("neighbors", "MTIzNDU2Nzg5MTEyMzQ1Njc4OTIxMjM0NTY3ODkzMTI:1.2.3.4:1234,MTIzNDU2Nzg5MTEyMzQ1Njc4OTIxMjM0NTY3ODkzMTI:5.6.7.8:5678", Set),
             #[cfg(not(target_os = "windows"))]
             ("real-user", "9999:9999:booga", Set),
+            ("secure-dns-servers", "tls://1.1.1.1", Set),
+            ("socks-credentials", "user:pass", Set),
+            ("socks-port", "1080", Set),
+            ("stream-idle-timeout", "300", Set),
         ]);
         let subject = SetupReporterReal::new();
 
         let result = subject.get_modified_setup(existing_setup, vec![]).unwrap();
 
         let expected_result = vec![
+            ("additional-clandestine-ports", "8443", Set),
             ("blockchain-service-url", "https://example.com", Set),
             ("chain", TEST_DEFAULT_CHAIN_NAME, Set),
             ("clandestine-port", "1234", Set),
@@ -1058,13 +1264,20 @@ mod tests {
             ("db-password", "password", Set),
             ("dns-servers", "8.8.8.8", Set),
             ("earning-wallet", "0x0123456789012345678901234567890123456789", Set),
+            ("encrypted-sni", "prompt", Set),
+            ("exit-policy", "port:25", Set),
             ("gas-price", "50", Set),
             ("ip", "4.3.2.1", Set),
             ("log-level", "error", Set),
+            ("multipath", "stripe", Set),
             ("neighborhood-mode", "originate-only", Set),
             ("neighbors", "MTIzNDU2Nzg5MTEyMzQ1Njc4OTIxMjM0NTY3ODkzMTI:1.2.3.4:1234,MTIzNDU2Nzg5MTEyMzQ1Njc4OTIxMjM0NTY3ODkzMTI:5.6.7.8:5678", Set),
             #[cfg(not(target_os = "windows"))]
             ("real-user", "9999:9999:booga", Set),
+            ("secure-dns-servers", "tls://1.1.1.1", Set),
+            ("socks-credentials", "user:pass", Set),
+            ("socks-port", "1080", Set),
+            ("stream-idle-timeout", "300", Set),
         ].into_iter()
             .map (|(name, value, status)| (name.to_string(), UiSetupResponseValue::new(name, value, status)))
             .collect_vec();
@@ -1083,6 +1296,7 @@ mod tests {
             "get_modified_setup_database_nonexistent_everything_set",
         );
         let incoming_setup = vec![
+            ("additional-clandestine-ports", "8443"),
             ("blockchain-service-url", "https://example.com"),
             ("chain", TEST_DEFAULT_CHAIN_NAME),
             ("clandestine-port", "1234"),
@@ -1092,13 +1306,20 @@ mod tests {
             ("db-password", "password"),
             ("dns-servers", "8.8.8.8"),
             ("earning-wallet", "0x0123456789012345678901234567890123456789"),
+            ("encrypted-sni", "prompt"),
+            ("exit-policy", "port:25"),
             ("gas-price", "50"),
             ("ip", "4.3.2.1"),
             ("log-level", "error"),
+            ("multipath", "stripe"),
             ("neighborhood-mode", "originate-only"),
             ("neighbors", "MTIzNDU2Nzg5MTEyMzQ1Njc4OTIxMjM0NTY3ODkzMTI:1.2.3.4:1234,MTIzNDU2Nzg5MTEyMzQ1Njc4OTIxMjM0NTY3ODkzMTI:5.6.7.8:5678"),
             #[cfg(not(target_os = "windows"))]
             ("real-user", "9999:9999:booga"),
+            ("secure-dns-servers", "tls://1.1.1.1"),
+            ("socks-credentials", "user:pass"),
+            ("socks-port", "1080"),
+            ("stream-idle-timeout", "300"),
         ].into_iter()
             .map (|(name, value)| UiSetupRequestValue::new(name, value))
             .collect_vec();
@@ -1109,6 +1330,7 @@ mod tests {
             .unwrap();
 
         let expected_result = vec![
+            ("additional-clandestine-ports", "8443", Set),
             ("blockchain-service-url", "https://example.com", Set),
             ("chain", TEST_DEFAULT_CHAIN_NAME, Set),
             ("clandestine-port", "1234", Set),
@@ -1119,13 +1341,20 @@ mod tests {
             ("db-password", "password", Set),
             ("dns-servers", "8.8.8.8", Set),
             ("earning-wallet", "0x0123456789012345678901234567890123456789", Set),
+            ("encrypted-sni", "prompt", Set),
+            ("exit-policy", "port:25", Set),
             ("gas-price", "50", Set),
             ("ip", "4.3.2.1", Set),
             ("log-level", "error", Set),
+            ("multipath", "stripe", Set),
             ("neighborhood-mode", "originate-only", Set),
             ("neighbors", "MTIzNDU2Nzg5MTEyMzQ1Njc4OTIxMjM0NTY3ODkzMTI:1.2.3.4:1234,MTIzNDU2Nzg5MTEyMzQ1Njc4OTIxMjM0NTY3ODkzMTI:5.6.7.8:5678", Set),
             #[cfg(not(target_os = "windows"))]
             ("real-user", "9999:9999:booga", Set),
+            ("secure-dns-servers", "tls://1.1.1.1", Set),
+            ("socks-credentials", "user:pass", Set),
+            ("socks-port", "1080", Set),
+            ("stream-idle-timeout", "300", Set),
         ].into_iter()
             .map (|(name, value, status)| (name.to_string(), UiSetupResponseValue::new(name, value, status)))
             .collect_vec();
@@ -1145,6 +1374,7 @@ mod tests {
             "get_modified_setup_database_nonexistent_nothing_set_everything_in_environment",
         );
         vec![
+            ("MASQ_ADDITIONAL_CLANDESTINE_PORTS", "8443"),
             ("MASQ_BLOCKCHAIN_SERVICE_URL", "https://example.com"),
             ("MASQ_CHAIN", TEST_DEFAULT_CHAIN_NAME),
             ("MASQ_CLANDESTINE_PORT", "1234"),
@@ -1154,13 +1384,20 @@ mod tests {
             ("MASQ_DB_PASSWORD", "password"),
             ("MASQ_DNS_SERVERS", "8.8.8.8"),
             ("MASQ_EARNING_WALLET", "0x0123456789012345678901234567890123456789"),
+            ("MASQ_ENCRYPTED_SNI", "prompt"),
+            ("MASQ_EXIT_POLICY", "port:25"),
             ("MASQ_GAS_PRICE", "50"),
             ("MASQ_IP", "4.3.2.1"),
             ("MASQ_LOG_LEVEL", "error"),
+            ("MASQ_MULTIPATH", "stripe"),
             ("MASQ_NEIGHBORHOOD_MODE", "originate-only"),
             ("MASQ_NEIGHBORS", "MTIzNDU2Nzg5MTEyMzQ1Njc4OTIxMjM0NTY3ODkzMTI:1.2.3.4:1234,MTIzNDU2Nzg5MTEyMzQ1Njc4OTIxMjM0NTY3ODkzMTI:5.6.7.8:5678"),
             #[cfg(not(target_os = "windows"))]
             ("MASQ_REAL_USER", "9999:9999:booga"),
+            ("MASQ_SECURE_DNS_SERVERS", "tls://1.1.1.1"),
+            ("MASQ_SOCKS_CREDENTIALS", "user:pass"),
+            ("MASQ_SOCKS_PORT", "1080"),
+            ("MASQ_STREAM_IDLE_TIMEOUT", "300"),
         ].into_iter()
             .for_each (|(name, value)| std::env::set_var (name, value));
         let params = vec![];
@@ -1169,6 +1406,7 @@ mod tests {
         let result = subject.get_modified_setup(HashMap::new(), params).unwrap();
 
         let expected_result = vec![
+            ("additional-clandestine-ports", "8443", Configured),
             ("blockchain-service-url", "https://example.com", Configured),
             ("chain", TEST_DEFAULT_CHAIN_NAME, Configured),
             ("clandestine-port", "1234", Configured),
@@ -1179,13 +1417,20 @@ mod tests {
             ("db-password", "password", Configured),
             ("dns-servers", "8.8.8.8", Configured),
             ("earning-wallet", "0x0123456789012345678901234567890123456789", Configured),
+            ("encrypted-sni", "prompt", Configured),
+            ("exit-policy", "port:25", Configured),
             ("gas-price", "50", Configured),
             ("ip", "4.3.2.1", Configured),
             ("log-level", "error", Configured),
+            ("multipath", "stripe", Configured),
             ("neighborhood-mode", "originate-only", Configured),
             ("neighbors", "MTIzNDU2Nzg5MTEyMzQ1Njc4OTIxMjM0NTY3ODkzMTI:1.2.3.4:1234,MTIzNDU2Nzg5MTEyMzQ1Njc4OTIxMjM0NTY3ODkzMTI:5.6.7.8:5678", Configured),
             #[cfg(not(target_os = "windows"))]
             ("real-user", "9999:9999:booga", Configured),
+            ("secure-dns-servers", "tls://1.1.1.1", Configured),
+            ("socks-credentials", "user:pass", Configured),
+            ("socks-port", "1080", Configured),
+            ("stream-idle-timeout", "300", Configured),
         ].into_iter()
             .map (|(name, value, status)| (name.to_string(), UiSetupResponseValue::new(name, value, status)))
             .collect_vec();
@@ -1271,6 +1516,7 @@ mod tests {
         let result = subject.get_modified_setup(existing_setup, params).unwrap();
 
         let expected_result = vec![
+            ("additional-clandestine-ports", "", Blank),
             (
                 "blockchain-service-url",
                 "https://www.ropsten.com",
@@ -1297,9 +1543,12 @@ mod tests {
                 "0xbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb",
                 Configured,
             ),
+            ("encrypted-sni", "", Blank),
+            ("exit-policy", "", Blank),
             ("gas-price", "88", Configured),
             ("ip", "", Blank),
             ("log-level", "debug", Configured),
+            ("multipath", "", Blank),
             ("neighborhood-mode", "zero-hop", Configured),
             ("neighbors", "", Blank),
             #[cfg(not(target_os = "windows"))]
@@ -1310,6 +1559,10 @@ mod tests {
                     .to_string(),
                 Default,
             ),
+            ("secure-dns-servers", "", Blank),
+            ("socks-credentials", "", Blank),
+            ("socks-port", "", Blank),
+            ("stream-idle-timeout", "", Blank),
         ]
         .into_iter()
         .map(|(name, value, status)| {
@@ -1334,6 +1587,7 @@ mod tests {
             "get_modified_setup_database_nonexistent_all_but_requireds_cleared",
         );
         vec![
+            ("MASQ_ADDITIONAL_CLANDESTINE_PORTS", "8443"),
             ("MASQ_BLOCKCHAIN_SERVICE_URL", "https://example.com"),
             ("MASQ_CHAIN", TEST_DEFAULT_CHAIN_NAME),
             ("MASQ_CLANDESTINE_PORT", "1234"),
@@ -1343,13 +1597,20 @@ mod tests {
             ("MASQ_DB_PASSWORD", "password"),
             ("MASQ_DNS_SERVERS", "8.8.8.8"),
             ("MASQ_EARNING_WALLET", "0x0123456789012345678901234567890123456789"),
+            ("MASQ_ENCRYPTED_SNI", "prompt"),
+            ("MASQ_EXIT_POLICY", "port:25"),
             ("MASQ_GAS_PRICE", "50"),
             ("MASQ_IP", "4.3.2.1"),
             ("MASQ_LOG_LEVEL", "error"),
+            ("MASQ_MULTIPATH", "stripe"),
             ("MASQ_NEIGHBORHOOD_MODE", "originate-only"),
             ("MASQ_NEIGHBORS", "MTIzNDU2Nzg5MTEyMzQ1Njc4OTIxMjM0NTY3ODkzMTI:1.2.3.4:1234,MTIzNDU2Nzg5MTEyMzQ1Njc4OTIxMjM0NTY3ODkzMTI:5.6.7.8:5678"),
             #[cfg(not(target_os = "windows"))]
             ("MASQ_REAL_USER", "9999:9999:booga"),
+            ("MASQ_SECURE_DNS_SERVERS", "tls://1.1.1.1"),
+            ("MASQ_SOCKS_CREDENTIALS", "user:pass"),
+            ("MASQ_SOCKS_PORT", "1080"),
+            ("MASQ_STREAM_IDLE_TIMEOUT", "300"),
         ].into_iter()
             .for_each (|(name, value)| std::env::set_var (name, value));
         let params = vec![
@@ -1405,6 +1666,7 @@ mod tests {
         let result = subject.get_modified_setup(existing_setup, params).unwrap();
 
         let expected_result = vec![
+            ("additional-clandestine-ports", "8443", Configured),
             ("blockchain-service-url", "https://example.com", Configured),
             ("chain", TEST_DEFAULT_CHAIN_NAME, Configured),
             ("clandestine-port", "1234", Configured),
@@ -1419,13 +1681,20 @@ mod tests {
                 "0x0123456789012345678901234567890123456789",
                 Configured,
             ),
+            ("encrypted-sni", "prompt", Configured),
+            ("exit-policy", "port:25", Configured),
             ("gas-price", "50", Configured),
             ("ip", "4.3.2.1", Configured),
             ("log-level", "error", Configured),
+            ("multipath", "stripe", Configured),
             ("neighborhood-mode", "originate-only", Configured),
             ("neighbors", "MTIzNDU2Nzg5MTEyMzQ1Njc4OTIxMjM0NTY3ODkzMTI:1.2.3.4:1234,MTIzNDU2Nzg5MTEyMzQ1Njc4OTIxMjM0NTY3ODkzMTI:5.6.7.8:5678", Configured),
             #[cfg(not(target_os = "windows"))]
             ("real-user", "9999:9999:booga", Configured),
+            ("secure-dns-servers", "tls://1.1.1.1", Configured),
+            ("socks-credentials", "user:pass", Configured),
+            ("socks-port", "1080", Configured),
+            ("stream-idle-timeout", "300", Configured),
         ]
         .into_iter()
         .map(|(name, value, status)| {
@@ -1568,6 +1837,86 @@ mod tests {
         );
     }
 
+    #[test]
+    fn get_modified_setup_flags_both_parameters_of_a_conflict() {
+        let _guard = EnvironmentGuard::new();
+        let home_dir = ensure_node_home_directory_exists(
+            "setup_reporter",
+            "get_modified_setup_flags_both_parameters_of_a_conflict",
+        );
+        let incoming_setup = vec![
+            ("data-directory", home_dir.to_str().unwrap()),
+            ("neighborhood-mode", "zero-hop"),
+            ("ip", "1.2.3.4"),
+        ]
+        .into_iter()
+        .map(|(name, value)| UiSetupRequestValue::new(name, value))
+        .collect_vec();
+        let subject = SetupReporterReal::new();
+
+        let (_, error) = subject
+            .get_modified_setup(HashMap::new(), incoming_setup)
+            .err()
+            .unwrap();
+
+        let expected = ConfiguratorError::conflict(
+            &["neighborhood-mode", "ip"],
+            "Node cannot run as --neighborhood-mode zero-hop if --ip is specified",
+        );
+        expected.param_errors.iter().for_each(|param_error| {
+            assert_eq!(
+                error.param_errors.contains(param_error),
+                true,
+                "{:?}",
+                error
+            )
+        });
+    }
+
+    #[test]
+    fn get_modified_setup_requires_db_password_for_consuming_wallet_derivation_path() {
+        let _guard = EnvironmentGuard::new();
+        let home_dir = ensure_node_home_directory_exists(
+            "setup_reporter",
+            "get_modified_setup_requires_db_password_for_consuming_wallet_derivation_path",
+        );
+        let conn = DbInitializerReal::new()
+            .initialize(&home_dir, chain_id_from_name(DEFAULT_CHAIN_NAME), true)
+            .unwrap();
+        let mut config = PersistentConfigurationReal::from(conn);
+        config.change_password(None, "password").unwrap();
+        config
+            .set_mnemonic_seed(b"booga booga", "password")
+            .unwrap();
+        config
+            .set_consuming_wallet_derivation_path("m/44'/60'/1'/2/3", "password")
+            .unwrap();
+        let incoming_setup = vec![
+            ("data-directory", home_dir.to_str().unwrap()),
+            ("ip", "4.3.2.1"),
+        ]
+        .into_iter()
+        .map(|(name, value)| UiSetupRequestValue::new(name, value))
+        .collect_vec();
+        let subject = SetupReporterReal::new();
+
+        let (_, error) = subject
+            .get_modified_setup(HashMap::new(), incoming_setup)
+            .err()
+            .unwrap();
+
+        assert_eq!(
+            error.param_errors.contains(&ParamError::with_code(
+                "db-password",
+                ParamErrorCode::Required,
+                "Database holds a consuming wallet derivation path, which can't be used without --db-password",
+            )),
+            true,
+            "{:?}",
+            error
+        );
+    }
+
     #[test]
     fn calculate_fundamentals_with_only_environment() {
         let _guard = EnvironmentGuard::new();
@@ -2061,6 +2410,7 @@ mod tests {
     fn data_directory_computed_default() {
         let real_user = RealUser::new(None, None, None).populate(&RealDirsWrapper {});
         let expected = data_directory_from_context(&RealDirsWrapper {}, &real_user, &None, "dev")
+            .unwrap()
             .to_string_lossy()
             .to_string();
         let mut config = BootstrapperConfig::new();
@@ -9,12 +9,16 @@ use crate::node_configurator::{
     app_head, data_directory_from_context, real_user_data_directory_opt_and_chain_name, DirsWrapper,
 };
 use crate::privilege_drop::{PrivilegeDropper, PrivilegeDropperReal};
+use crate::sub_lib::cli_output;
+use crate::sub_lib::cli_output::OutputFormat;
 use crate::sub_lib::utils::make_new_multi_config;
 use clap::Arg;
 use heck::MixedCase;
 use masq_lib::command::StdStreams;
 use masq_lib::multi_config::{CommandLineVcl, EnvironmentVcl, VirtualCommandLine};
-use masq_lib::shared_schema::{chain_arg, data_directory_arg, real_user_arg, ConfiguratorError};
+use masq_lib::shared_schema::{
+    chain_arg, data_directory_arg, output_arg, real_user_arg, ConfiguratorError,
+};
 use serde_json::json;
 use serde_json::{Map, Value};
 use std::path::PathBuf;
@@ -22,16 +26,52 @@ use std::path::PathBuf;
 const DUMP_CONFIG_HELP: &str =
     "Dump the configuration of MASQ Node to stdout in JSON. Used chiefly by UIs.";
 
+const REDACTED: &str = "<redacted>";
+
 pub fn dump_config(args: &[String], streams: &mut StdStreams) -> Result<i32, ConfiguratorError> {
-    let (real_user, data_directory, chain_id) = distill_args(&RealDirsWrapper {}, args, streams)?;
+    let mode_arg = Arg::with_name("dump-config")
+        .long("dump-config")
+        .required(true)
+        .takes_value(false)
+        .help(DUMP_CONFIG_HELP);
+    let (real_user, data_directory, chain_id) =
+        distill_args(&RealDirsWrapper {}, mode_arg, args, streams)?;
     PrivilegeDropperReal::new().drop_privileges(&real_user);
     let config_dao = make_config_dao(&data_directory, chain_id);
     let configuration = config_dao.get_all().expect("Couldn't fetch configuration");
-    let json = configuration_to_json(configuration);
-    write_string(streams, json);
+    match OutputFormat::from_args(args) {
+        OutputFormat::Human => write_string(streams, configuration_to_json(configuration)),
+        OutputFormat::Json => cli_output::write(
+            streams.stdout,
+            &cli_output::success("dumpConfig", configuration_to_value(configuration)),
+        ),
+    }
     Ok(0)
 }
 
+// What dump_config would show, except for the values of encrypted settings. Unlike dump_config,
+// it won't create a database that isn't there.
+pub fn redacted_config(data_directory: &PathBuf, chain_id: u8) -> Result<String, String> {
+    let conn = DbInitializerReal::new()
+        .initialize(data_directory, chain_id, false)
+        .map_err(|e| format!("Can't open database: {:?}", e))?;
+    let configuration = ConfigDaoReal::new(conn)
+        .get_all()
+        .map_err(|e| format!("Can't read configuration: {:?}", e))?;
+    Ok(configuration_to_json(
+        configuration
+            .into_iter()
+            .map(|record| match record.value_opt {
+                Some(_) if record.encrypted => ConfigDaoRecord {
+                    value_opt: Some(REDACTED.to_string()),
+                    ..record
+                },
+                _ => record,
+            })
+            .collect(),
+    ))
+}
+
 fn write_string(streams: &mut StdStreams, json: String) {
     streams
         .stdout
@@ -44,6 +84,11 @@ fn write_string(streams: &mut StdStreams, json: String) {
 }
 
 fn configuration_to_json(configuration: Vec<ConfigDaoRecord>) -> String {
+    serde_json::to_string_pretty(&configuration_to_value(configuration))
+        .expect("Couldn't serialize configuration to JSON")
+}
+
+fn configuration_to_value(configuration: Vec<ConfigDaoRecord>) -> Value {
     let mut map = Map::new();
     configuration.into_iter().for_each(|record| {
         let json_name = record.name.to_mixed_case();
@@ -52,8 +97,7 @@ fn configuration_to_json(configuration: Vec<ConfigDaoRecord>) -> String {
             Some(value) => map.insert(json_name, json!(value)),
         };
     });
-    let value: Value = Value::Object(map);
-    serde_json::to_string_pretty(&value).expect("Couldn't serialize configuration to JSON")
+    Value::Object(map)
 }
 
 fn make_config_dao(data_directory: &PathBuf, chain_id: u8) -> ConfigDaoReal {
@@ -69,21 +113,18 @@ fn make_config_dao(data_directory: &PathBuf, chain_id: u8) -> ConfigDaoReal {
     ConfigDaoReal::new(conn)
 }
 
-fn distill_args(
+// For modes that need to know nothing but where the database is and whose it is
+pub fn distill_args(
     dirs_wrapper: &dyn DirsWrapper,
+    mode_arg: Arg<'static, 'static>,
     args: &[String],
     streams: &mut StdStreams,
 ) -> Result<(RealUser, PathBuf, u8), ConfiguratorError> {
     let app = app_head()
-        .arg(
-            Arg::with_name("dump-config")
-                .long("dump-config")
-                .required(true)
-                .takes_value(false)
-                .help(DUMP_CONFIG_HELP),
-        )
+        .arg(mode_arg)
         .arg(chain_arg())
         .arg(data_directory_arg())
+        .arg(output_arg())
         .arg(real_user_arg());
     let vcls: Vec<Box<dyn VirtualCommandLine>> = vec![
         Box::new(CommandLineVcl::new(args.to_vec())),
@@ -93,7 +134,7 @@ fn distill_args(
     let (real_user, data_directory_opt, chain_name) =
         real_user_data_directory_opt_and_chain_name(dirs_wrapper, &multi_config);
     let directory =
-        data_directory_from_context(dirs_wrapper, &real_user, &data_directory_opt, &chain_name);
+        data_directory_from_context(dirs_wrapper, &real_user, &data_directory_opt, &chain_name)?;
     Ok((real_user, directory, chain_id_from_name(&chain_name)))
 }
 
@@ -141,15 +182,27 @@ mod tests {
             other => panic!("Was expecting Value::Object, got {:?} instead", other),
         };
         let expected_value = json!({
+           "applicationRules": null,
+           "clandestineKeys": null,
            "clandestinePort": actual_map.get ("clandestinePort"),
            "consumingWalletDerivationPath": null,
            "consumingWalletPublicKey": null,
+           "dbPasswordFailures": "0",
+           "dbPasswordRetryAfter": "0",
            "earningWalletAddress": null,
+           "earningWalletChangeBlock": null,
+           "earningWalletChangeSignature": null,
            "exampleEncrypted": null,
            "gasPrice": "1",
+           "logRetention": "50",
+           "logRotationAgeHours": "0",
+           "logRotationSizeMb": "100",
            "pastNeighbors": null,
+           "paymentToken": null,
+           "previousEarningWalletAddress": null,
            "schemaVersion": CURRENT_SCHEMA_VERSION,
            "seed": null,
+           "splitTunnelRules": null,
            "startBlock": &contract_creation_block_from_chain_id(chain_id_from_name(TEST_DEFAULT_CHAIN_NAME)).to_string(),
         });
         assert_eq!(actual_value, expected_value);
@@ -191,17 +244,128 @@ mod tests {
         let output = holder.stdout.get_string();
         let actual_value: Value = serde_json::from_str(&output).unwrap();
         let expected_value = json!({
+           "applicationRules": null,
+           "clandestineKeys": null,
            "clandestinePort": "3456",
            "consumingWalletDerivationPath": null,
            "consumingWalletPublicKey": "01020304",
+           "dbPasswordFailures": "0",
+           "dbPasswordRetryAfter": "0",
            "earningWalletAddress": "0x0123456789012345678901234567890123456789",
+           "earningWalletChangeBlock": null,
+           "earningWalletChangeSignature": null,
            "exampleEncrypted": null,
            "gasPrice": "1",
+           "logRetention": "50",
+           "logRotationAgeHours": "0",
+           "logRotationSizeMb": "100",
            "pastNeighbors": null,
+           "paymentToken": null,
+           "previousEarningWalletAddress": null,
            "schemaVersion": CURRENT_SCHEMA_VERSION,
            "seed": null,
+           "splitTunnelRules": null,
            "startBlock": &contract_creation_block_from_chain_id(chain_id_from_name(TEST_DEFAULT_CHAIN_NAME)).to_string(),
         });
         assert_eq!(actual_value, expected_value);
     }
+
+    #[test]
+    fn dump_config_dumps_earning_only_database_without_key_material() {
+        let _clap_guard = ClapGuard::new();
+        let data_dir = ensure_node_home_directory_exists(
+            "config_dumper",
+            "dump_config_dumps_earning_only_database_without_key_material",
+        )
+        .join("Substratum")
+        .join(TEST_DEFAULT_CHAIN_NAME);
+        let mut holder = FakeStreamHolder::new();
+        {
+            let conn = DbInitializerReal::new()
+                .initialize(&data_dir, DEFAULT_CHAIN_ID, true)
+                .unwrap();
+            let mut persistent_config = PersistentConfigurationReal::from(conn);
+            persistent_config
+                .set_earning_wallet_address("0x0123456789012345678901234567890123456789")
+                .unwrap();
+        }
+        let args_vec: Vec<String> = ArgsBuilder::new()
+            .param("--data-directory", data_dir.to_str().unwrap())
+            .param("--real-user", "123::")
+            .param("--chain", TEST_DEFAULT_CHAIN_NAME)
+            .opt("--dump-config")
+            .into();
+
+        let result = dump_config(args_vec.as_slice(), &mut holder.streams()).unwrap();
+
+        assert_eq!(result, 0);
+        let output = holder.stdout.get_string();
+        let actual_value: Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(
+            actual_value["earningWalletAddress"],
+            json!("0x0123456789012345678901234567890123456789")
+        );
+        assert_eq!(actual_value["seed"], Value::Null);
+        assert_eq!(actual_value["consumingWalletDerivationPath"], Value::Null);
+        assert_eq!(actual_value["consumingWalletPublicKey"], Value::Null);
+    }
+
+    #[test]
+    fn dump_config_wraps_the_configuration_for_json_output() {
+        let data_dir = ensure_node_home_directory_exists(
+            "config_dumper",
+            "dump_config_wraps_the_configuration_for_json_output",
+        )
+        .join("Substratum")
+        .join(TEST_DEFAULT_CHAIN_NAME);
+        let mut holder = FakeStreamHolder::new();
+        let args_vec: Vec<String> = ArgsBuilder::new()
+            .param("--data-directory", data_dir.to_str().unwrap())
+            .param("--real-user", "123::")
+            .param("--chain", TEST_DEFAULT_CHAIN_NAME)
+            .param("--output", "json")
+            .opt("--dump-config")
+            .into();
+
+        let result = dump_config(args_vec.as_slice(), &mut holder.streams()).unwrap();
+
+        assert_eq!(result, 0);
+        let output = holder.stdout.get_string();
+        let actual_value: Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(actual_value["version"], json!(1));
+        assert_eq!(actual_value["mode"], json!("dumpConfig"));
+        assert_eq!(actual_value["result"]["gasPrice"], json!("1"));
+        assert_eq!(
+            actual_value["result"]["schemaVersion"],
+            json!(CURRENT_SCHEMA_VERSION)
+        );
+    }
+
+    #[test]
+    fn redacted_config_hides_encrypted_values_and_creates_no_database() {
+        let data_dir = ensure_node_home_directory_exists(
+            "config_dumper",
+            "redacted_config_hides_encrypted_values_and_creates_no_database",
+        );
+        let missing = redacted_config(&data_dir, DEFAULT_CHAIN_ID);
+        {
+            let conn = DbInitializerReal::new()
+                .initialize(&data_dir, DEFAULT_CHAIN_ID, true)
+                .unwrap();
+            let mut persistent_config = PersistentConfigurationReal::from(conn);
+            persistent_config.change_password(None, "password").unwrap();
+            persistent_config.set_clandestine_port(3456).unwrap();
+        }
+
+        let result = redacted_config(&data_dir, DEFAULT_CHAIN_ID).unwrap();
+
+        assert_eq!(
+            missing.err().unwrap().starts_with("Can't open database: "),
+            true
+        );
+        let actual_value: Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(actual_value["exampleEncrypted"], json!("<redacted>"));
+        assert_eq!(actual_value["seed"], json!(null));
+        assert_eq!(actual_value["clandestinePort"], json!("3456"));
+    }
 }
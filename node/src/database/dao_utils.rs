@@ -2,7 +2,9 @@
 use crate::accountant::jackass_unsigned_to_signed;
 use crate::database::connection_wrapper::ConnectionWrapper;
 use crate::database::db_initializer::{connection_or_panic, DbInitializerReal};
+use crate::sub_lib::clock::{Clock, ClockReal};
 use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::Duration;
 use std::time::SystemTime;
 
@@ -26,6 +28,8 @@ pub struct DaoFactoryReal {
     pub data_directory: PathBuf,
     pub chain_id: u8,
     pub create_if_necessary: bool,
+    // Stamps the records the DAOs it makes write, and tells them how old records are
+    pub clock: Arc<dyn Clock>,
 }
 
 impl DaoFactoryReal {
@@ -34,6 +38,7 @@ impl DaoFactoryReal {
             data_directory: data_directory.clone(),
             chain_id,
             create_if_necessary,
+            clock: Arc::new(ClockReal {}),
         }
     }
 
@@ -4,6 +4,11 @@ use crate::blockchain::blockchain_interface::{
 };
 use crate::database::connection_wrapper::{ConnectionWrapper, ConnectionWrapperReal};
 use crate::db_config::secure_config_layer::EXAMPLE_ENCRYPTED;
+use crate::sub_lib::deterministic;
+use crate::sub_lib::log_rotation::{
+    DEFAULT_LOG_RETENTION, DEFAULT_LOG_ROTATION_AGE_HOURS, DEFAULT_LOG_ROTATION_SIZE_MB,
+};
+use crate::sub_lib::logger::Logger;
 use masq_lib::constants::{
     DEFAULT_GAS_PRICE, HIGHEST_RANDOM_CLANDESTINE_PORT, LOWEST_USABLE_INSECURE_PORT,
 };
@@ -19,7 +24,7 @@ use std::path::PathBuf;
 use tokio::net::TcpListener;
 
 pub const DATABASE_FILE: &str = "node-data.db";
-pub const CURRENT_SCHEMA_VERSION: &str = "0.0.10";
+pub const CURRENT_SCHEMA_VERSION: &str = "0.0.19";
 
 #[derive(Debug, PartialEq)]
 pub enum InitializationError {
@@ -58,6 +63,7 @@ impl DbInitializer for DbInitializerReal {
         match Connection::open_with_flags(database_file_path, flags) {
             Ok(conn) => {
                 eprintln!("Opened existing database at {:?}", database_file_path);
+                Self::use_write_ahead_log(&conn);
                 let config = self.extract_configurations(&conn);
                 match self.check_version(config.get("schema_version")) {
                     Ok(_) => Ok(Box::new(ConnectionWrapperReal::new(conn))),
@@ -71,6 +77,7 @@ impl DbInitializer for DbInitializerReal {
                 match Connection::open_with_flags(database_file_path, flags) {
                     Ok(conn) => {
                         eprintln!("Created new database at {:?}", database_file_path);
+                        Self::use_write_ahead_log(&conn);
                         match self.create_database_tables(&conn, chain_id) {
                             Ok(()) => Ok(Box::new(ConnectionWrapperReal::new(conn))),
                             Err(e) => Err(e),
@@ -88,6 +95,29 @@ impl DbInitializerReal {
         Self::default()
     }
 
+    // So that a reader's transaction sees one snapshot of the database from start to finish
+    // without locking writers out for as long as it lasts. The mode sticks to the database file,
+    // so this changes something only the first time; without it, snapshots are still consistent,
+    // but writers can't commit while one is being read.
+    fn use_write_ahead_log(conn: &Connection) {
+        let logger = Logger::new("DbInitializer");
+        match conn.query_row("pragma journal_mode = wal", NO_PARAMS, |row| {
+            row.get::<usize, String>(0)
+        }) {
+            Ok(ref mode) if mode.as_str() == "wal" => (),
+            Ok(mode) => warning!(
+                logger,
+                "Could not switch the database to write-ahead logging: it's still in {} mode",
+                mode
+            ),
+            Err(e) => warning!(
+                logger,
+                "Could not switch the database to write-ahead logging: {:?}",
+                e
+            ),
+        }
+    }
+
     fn is_creation_necessary(data_directory: &PathBuf) -> bool {
         match fs::read_dir(data_directory) {
             Ok(_) => !data_directory.join(DATABASE_FILE).exists(),
@@ -121,7 +151,11 @@ impl DbInitializerReal {
         self.initialize_config(conn, chain_id)?;
         self.create_payable_table(conn)?;
         self.create_receivable_table(conn)?;
-        self.create_banned_table(conn)
+        self.create_banned_table(conn)?;
+        self.create_usage_history_table(conn)?;
+        self.create_connection_audit_table(conn)?;
+        self.create_trust_table(conn)?;
+        self.create_balance_history_table(conn)
     }
 
     fn create_config_table(&self, conn: &Connection) -> Result<(), InitializationError> {
@@ -169,6 +203,20 @@ impl DbInitializerReal {
             false,
             "public key for the consuming wallet private key",
         );
+        Self::set_config_value(
+            conn,
+            "db_password_failures",
+            Some("0"),
+            false,
+            "incorrect database passwords in a row",
+        );
+        Self::set_config_value(
+            conn,
+            "db_password_retry_after",
+            Some("0"),
+            false,
+            "seconds since the epoch before which no database password may be tried",
+        );
         Self::set_config_value(
             conn,
             "earning_wallet_address",
@@ -176,6 +224,27 @@ impl DbInitializerReal {
             false,
             "earning wallet address",
         );
+        Self::set_config_value(
+            conn,
+            "earning_wallet_change_block",
+            None,
+            false,
+            "block from which the earning wallet address replaced the previous one",
+        );
+        Self::set_config_value(
+            conn,
+            "earning_wallet_change_signature",
+            None,
+            false,
+            "previous earning wallet's signature of the change",
+        );
+        Self::set_config_value(
+            conn,
+            "previous_earning_wallet_address",
+            None,
+            false,
+            "earning wallet address before the last change",
+        );
         Self::set_config_value(
             conn,
             "schema_version",
@@ -198,7 +267,50 @@ impl DbInitializerReal {
             false,
             "gas price",
         );
+        Self::set_config_value(conn, "application_rules", None, false, "application rules");
+        Self::set_config_value(
+            conn,
+            "log_rotation_size_mb",
+            Some(&DEFAULT_LOG_ROTATION_SIZE_MB.to_string()),
+            false,
+            "log rotation size in megabytes",
+        );
+        Self::set_config_value(
+            conn,
+            "log_rotation_age_hours",
+            Some(&DEFAULT_LOG_ROTATION_AGE_HOURS.to_string()),
+            false,
+            "log rotation age in hours",
+        );
+        Self::set_config_value(
+            conn,
+            "log_retention",
+            Some(&DEFAULT_LOG_RETENTION.to_string()),
+            false,
+            "number of log archives to keep",
+        );
         Self::set_config_value(conn, "past_neighbors", None, true, "past neighbors");
+        Self::set_config_value(
+            conn,
+            "payment_token",
+            None,
+            false,
+            "token the accounts are kept in, with its decimals",
+        );
+        Self::set_config_value(
+            conn,
+            "clandestine_keys",
+            None,
+            true,
+            "active and previous clandestine keys",
+        );
+        Self::set_config_value(
+            conn,
+            "split_tunnel_rules",
+            None,
+            false,
+            "split-tunnel rules",
+        );
         Ok(())
     }
 
@@ -253,6 +365,76 @@ impl DbInitializerReal {
         Ok(())
     }
 
+    fn create_usage_history_table(&self, conn: &Connection) -> Result<(), InitializationError> {
+        conn.execute(
+            "create table usage_history (
+                day text primary key,
+                uptime_seconds integer not null,
+                routed_bytes integer not null,
+                earned_gwei integer not null,
+                neighbors_gained integer not null,
+                neighbors_lost integer not null
+            )",
+            NO_PARAMS,
+        )
+        .expect("Can't create usage_history table");
+        Ok(())
+    }
+
+    fn create_connection_audit_table(&self, conn: &Connection) -> Result<(), InitializationError> {
+        conn.execute(
+            "create table connection_audit (
+                public_key blob null,
+                peer_addr text not null,
+                direction text not null,
+                opened_at integer not null,
+                duration_ms integer not null,
+                bytes_in integer not null,
+                bytes_out integer not null,
+                disconnect_reason text not null
+            )",
+            NO_PARAMS,
+        )
+        .expect("Can't create connection_audit table");
+        conn.execute(
+            "create index idx_connection_audit_opened_at on connection_audit (opened_at)",
+            NO_PARAMS,
+        )
+        .expect("Can't create connection_audit opened_at index");
+        Ok(())
+    }
+
+    fn create_trust_table(&self, conn: &Connection) -> Result<(), InitializationError> {
+        conn.execute(
+            "create table trust (
+                wallet_address text primary key,
+                payments_on_time integer not null,
+                payments_overdue integer not null,
+                accurate_gossip integer not null,
+                inaccurate_gossip integer not null,
+                uptime_seconds integer not null
+            )",
+            NO_PARAMS,
+        )
+        .expect("Can't create trust table");
+        Ok(())
+    }
+
+    fn create_balance_history_table(&self, conn: &Connection) -> Result<(), InitializationError> {
+        conn.execute(
+            "create table balance_history (
+                day text primary key,
+                wallet_address text not null,
+                gas_balance text not null,
+                masq_balance text not null,
+                masq_price text null
+            )",
+            NO_PARAMS,
+        )
+        .expect("Can't create balance_history table");
+        Ok(())
+    }
+
     fn extract_configurations(&self, conn: &Connection) -> HashMap<String, Option<String>> {
         let mut stmt = conn.prepare("select name, value from config").unwrap();
         let query_result = stmt.query_map(NO_PARAMS, |row| Ok((row.get(0), row.get(1))));
@@ -291,8 +473,8 @@ impl DbInitializerReal {
         }
     }
 
-    fn choose_clandestine_port() -> u16 {
-        let mut rng = SmallRng::from_entropy();
+    pub fn choose_clandestine_port() -> u16 {
+        let mut rng = SmallRng::seed_from_u64(deterministic::random_u64("clandestine port"));
         loop {
             let candidate_port: u16 =
                 rng.gen_range(LOWEST_USABLE_INSECURE_PORT, HIGHEST_RANDOM_CLANDESTINE_PORT);
@@ -441,6 +623,7 @@ pub mod test_utils {
 mod tests {
     use super::*;
     use crate::blockchain::blockchain_interface::chain_id_from_name;
+    use crate::test_utils::logging::{init_test_logging, TestLogHandler};
     use masq_lib::constants::{
         DEFAULT_GAS_PRICE, HIGHEST_RANDOM_CLANDESTINE_PORT, LOWEST_USABLE_INSECURE_PORT,
     };
@@ -558,6 +741,138 @@ mod tests {
         assert!(banned_contents.next().is_none());
     }
 
+    #[test]
+    fn db_initialize_creates_usage_history_table() {
+        let home_dir = ensure_node_home_directory_does_not_exist(
+            "db_initializer",
+            "db_initialize_creates_usage_history_table",
+        );
+        let subject = DbInitializerReal::new();
+
+        subject
+            .initialize(&home_dir, DEFAULT_CHAIN_ID, true)
+            .unwrap();
+
+        let mut flags = OpenFlags::empty();
+        flags.insert(OpenFlags::SQLITE_OPEN_READ_ONLY);
+        let conn = Connection::open_with_flags(&home_dir.join(DATABASE_FILE), flags).unwrap();
+
+        let mut stmt = conn
+            .prepare("select day, uptime_seconds, routed_bytes, earned_gwei, neighbors_gained, neighbors_lost from usage_history")
+            .unwrap();
+        let mut usage_history_contents = stmt.query_map(NO_PARAMS, |_| Ok(())).unwrap();
+        assert!(usage_history_contents.next().is_none());
+    }
+
+    #[test]
+    fn db_initialize_creates_connection_audit_table() {
+        let home_dir = ensure_node_home_directory_does_not_exist(
+            "db_initializer",
+            "db_initialize_creates_connection_audit_table",
+        );
+        let subject = DbInitializerReal::new();
+
+        subject
+            .initialize(&home_dir, DEFAULT_CHAIN_ID, true)
+            .unwrap();
+
+        let mut flags = OpenFlags::empty();
+        flags.insert(OpenFlags::SQLITE_OPEN_READ_ONLY);
+        let conn = Connection::open_with_flags(&home_dir.join(DATABASE_FILE), flags).unwrap();
+
+        let mut stmt = conn
+            .prepare("select public_key, peer_addr, direction, opened_at, duration_ms, bytes_in, bytes_out, disconnect_reason from connection_audit")
+            .unwrap();
+        let mut connection_audit_contents = stmt.query_map(NO_PARAMS, |_| Ok(())).unwrap();
+        assert!(connection_audit_contents.next().is_none());
+    }
+
+    #[test]
+    fn db_initialize_creates_trust_table() {
+        let home_dir = ensure_node_home_directory_does_not_exist(
+            "db_initializer",
+            "db_initialize_creates_trust_table",
+        );
+        let subject = DbInitializerReal::new();
+
+        subject
+            .initialize(&home_dir, DEFAULT_CHAIN_ID, true)
+            .unwrap();
+
+        let mut flags = OpenFlags::empty();
+        flags.insert(OpenFlags::SQLITE_OPEN_READ_ONLY);
+        let conn = Connection::open_with_flags(&home_dir.join(DATABASE_FILE), flags).unwrap();
+
+        let mut stmt = conn
+            .prepare("select wallet_address, payments_on_time, payments_overdue, accurate_gossip, inaccurate_gossip, uptime_seconds from trust")
+            .unwrap();
+        let mut trust_contents = stmt.query_map(NO_PARAMS, |_| Ok(())).unwrap();
+        assert!(trust_contents.next().is_none());
+    }
+
+    #[test]
+    fn db_initialize_creates_balance_history_table() {
+        let home_dir = ensure_node_home_directory_does_not_exist(
+            "db_initializer",
+            "db_initialize_creates_balance_history_table",
+        );
+        let subject = DbInitializerReal::new();
+
+        subject
+            .initialize(&home_dir, DEFAULT_CHAIN_ID, true)
+            .unwrap();
+
+        let mut flags = OpenFlags::empty();
+        flags.insert(OpenFlags::SQLITE_OPEN_READ_ONLY);
+        let conn = Connection::open_with_flags(&home_dir.join(DATABASE_FILE), flags).unwrap();
+
+        let mut stmt = conn
+            .prepare("select day, wallet_address, gas_balance, masq_balance, masq_price from balance_history")
+            .unwrap();
+        let mut balance_history_contents = stmt.query_map(NO_PARAMS, |_| Ok(())).unwrap();
+        assert!(balance_history_contents.next().is_none());
+    }
+
+    #[test]
+    fn db_initialize_switches_the_database_to_write_ahead_logging() {
+        let home_dir = ensure_node_home_directory_does_not_exist(
+            "db_initializer",
+            "db_initialize_switches_the_database_to_write_ahead_logging",
+        );
+        let subject = DbInitializerReal::new();
+
+        subject
+            .initialize(&home_dir, DEFAULT_CHAIN_ID, true)
+            .unwrap();
+
+        let conn = Connection::open(&home_dir.join(DATABASE_FILE)).unwrap();
+        let journal_mode: String = conn
+            .query_row("pragma journal_mode", NO_PARAMS, |row| row.get(0))
+            .unwrap();
+        assert_eq!(journal_mode, "wal".to_string());
+    }
+
+    #[test]
+    fn failing_to_switch_to_write_ahead_logging_is_logged() {
+        init_test_logging();
+        let home_dir = ensure_node_home_directory_exists(
+            "db_initializer",
+            "failing_to_switch_to_write_ahead_logging_is_logged",
+        );
+        let db_path = home_dir.join("rollback-journal.db");
+        Connection::open(&db_path)
+            .unwrap()
+            .execute("create table t (x integer)", NO_PARAMS)
+            .unwrap();
+        let conn = Connection::open_with_flags(&db_path, OpenFlags::SQLITE_OPEN_READ_ONLY).unwrap();
+
+        DbInitializerReal::use_write_ahead_log(&conn);
+
+        TestLogHandler::new().exists_log_containing(
+            "WARN: DbInitializer: Could not switch the database to write-ahead logging",
+        );
+    }
+
     #[test]
     fn existing_database_with_correct_version_is_accepted_without_changes() {
         let home_dir = ensure_node_home_directory_exists(
@@ -601,23 +916,47 @@ mod tests {
             assert_eq!(actual_name, expected_name);
             value
         };
+        verify(&mut config_vec, "application_rules", None);
+        verify(&mut config_vec, "clandestine_keys", None);
         let clandestine_port_str_opt = verify_name(&mut config_vec, "clandestine_port");
         let clandestine_port: u16 = clandestine_port_str_opt.unwrap().parse().unwrap();
         assert!(clandestine_port >= 1025);
         assert!(clandestine_port < 10000);
         verify(&mut config_vec, "consuming_wallet_derivation_path", None);
         verify(&mut config_vec, "consuming_wallet_public_key", None);
+        verify(&mut config_vec, "db_password_failures", Some("0"));
+        verify(&mut config_vec, "db_password_retry_after", Some("0"));
         verify(&mut config_vec, "earning_wallet_address", None);
+        verify(&mut config_vec, "earning_wallet_change_block", None);
+        verify(&mut config_vec, "earning_wallet_change_signature", None);
         verify(&mut config_vec, EXAMPLE_ENCRYPTED, None);
         verify(&mut config_vec, "gas_price", Some(DEFAULT_GAS_PRICE));
+        verify(
+            &mut config_vec,
+            "log_retention",
+            Some(&DEFAULT_LOG_RETENTION.to_string()),
+        );
+        verify(
+            &mut config_vec,
+            "log_rotation_age_hours",
+            Some(&DEFAULT_LOG_ROTATION_AGE_HOURS.to_string()),
+        );
+        verify(
+            &mut config_vec,
+            "log_rotation_size_mb",
+            Some(&DEFAULT_LOG_ROTATION_SIZE_MB.to_string()),
+        );
         verify(&mut config_vec, "past_neighbors", None);
+        verify(&mut config_vec, "payment_token", None);
         verify(&mut config_vec, "preexisting", Some("yes")); // makes sure we just created this database
+        verify(&mut config_vec, "previous_earning_wallet_address", None);
         verify(
             &mut config_vec,
             "schema_version",
             Some(CURRENT_SCHEMA_VERSION),
         );
         verify(&mut config_vec, "seed", None);
+        verify(&mut config_vec, "split_tunnel_rules", None);
         verify(
             &mut config_vec,
             "start_block",
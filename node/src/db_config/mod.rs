@@ -1,6 +1,7 @@
 // Copyright (c) 2019-2020, MASQ (https://masq.ai). All rights reserved.
 
 pub mod config_dao;
+pub mod password_budget;
 pub mod persistent_configuration;
 pub mod secure_config_layer;
 pub mod typed_config_layer;
@@ -0,0 +1,132 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai). All rights reserved.
+
+use crate::db_config::persistent_configuration::{PersistentConfigError, PersistentConfiguration};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Incorrect db-passwords in a row that cost nothing but the time it takes to type them
+pub const FREE_PASSWORD_FAILURES: u64 = 3;
+// Each failure after the free ones doubles the wait before the next try, up to the maximum
+pub const FIRST_PASSWORD_COOLDOWN_SECS: u64 = 30;
+pub const MAX_PASSWORD_COOLDOWN_SECS: u64 = 60 * 60;
+
+pub fn password_cooldown_secs(failures: u64) -> u64 {
+    if failures <= FREE_PASSWORD_FAILURES {
+        return 0;
+    }
+    let doublings = (failures - FREE_PASSWORD_FAILURES - 1).min(32);
+    (FIRST_PASSWORD_COOLDOWN_SECS << doublings).min(MAX_PASSWORD_COOLDOWN_SECS)
+}
+
+// Like check_password with a password, except that it refuses to check one at all while an earlier
+// failure's cooldown is running. The failures are kept in the database, so starting the Node again
+// doesn't reset them; a correct password does.
+pub fn check_password_within_budget(
+    persistent_config: &mut dyn PersistentConfiguration,
+    db_password: &str,
+    now: SystemTime,
+) -> Result<bool, PersistentConfigError> {
+    let now_secs = now
+        .duration_since(UNIX_EPOCH)
+        .expect("System clock is before the epoch")
+        .as_secs();
+    let (failures, retry_after) = persistent_config.db_password_failures()?;
+    if now_secs < retry_after {
+        return Err(PersistentConfigError::PasswordCooldown(
+            retry_after - now_secs,
+        ));
+    }
+    if persistent_config.check_password(Some(db_password))? {
+        if failures > 0 {
+            persistent_config.set_db_password_failures(0, 0)?;
+        }
+        Ok(true)
+    } else {
+        let failures = failures + 1;
+        persistent_config
+            .set_db_password_failures(failures, now_secs + password_cooldown_secs(failures))?;
+        Ok(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::persistent_configuration_mock::PersistentConfigurationMock;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    fn at(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn cooldowns_start_after_the_free_failures_and_double_up_to_the_maximum() {
+        let cooldowns = (1..=12).map(password_cooldown_secs).collect::<Vec<u64>>();
+
+        assert_eq!(
+            cooldowns,
+            vec![0, 0, 0, 30, 60, 120, 240, 480, 960, 1920, 3600, 3600]
+        );
+        assert_eq!(password_cooldown_secs(u64::MAX), MAX_PASSWORD_COOLDOWN_SECS);
+    }
+
+    #[test]
+    fn a_failure_is_counted_and_sets_the_cooldown_for_the_next_try() {
+        let set_params_arc = Arc::new(Mutex::new(vec![]));
+        let mut persistent_config = PersistentConfigurationMock::new()
+            .db_password_failures_result(Ok((3, 1000)))
+            .check_password_result(Ok(false))
+            .set_db_password_failures_params(&set_params_arc);
+
+        let result = check_password_within_budget(&mut persistent_config, "guess", at(1000));
+
+        assert_eq!(result, Ok(false));
+        let set_params = set_params_arc.lock().unwrap();
+        assert_eq!(*set_params, vec![(4, 1030)]);
+    }
+
+    #[test]
+    fn no_password_is_checked_during_a_cooldown() {
+        let check_password_params_arc = Arc::new(Mutex::new(vec![]));
+        let set_params_arc = Arc::new(Mutex::new(vec![]));
+        let mut persistent_config = PersistentConfigurationMock::new()
+            .db_password_failures_result(Ok((5, 1060)))
+            .check_password_params(&check_password_params_arc)
+            .set_db_password_failures_params(&set_params_arc);
+
+        let result = check_password_within_budget(&mut persistent_config, "guess", at(1000));
+
+        assert_eq!(result, Err(PersistentConfigError::PasswordCooldown(60)));
+        assert!(check_password_params_arc.lock().unwrap().is_empty());
+        assert!(set_params_arc.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn a_correct_password_clears_the_failures() {
+        let check_password_params_arc = Arc::new(Mutex::new(vec![]));
+        let set_params_arc = Arc::new(Mutex::new(vec![]));
+        let mut persistent_config = PersistentConfigurationMock::new()
+            .db_password_failures_result(Ok((5, 1060)))
+            .check_password_params(&check_password_params_arc)
+            .check_password_result(Ok(true))
+            .set_db_password_failures_params(&set_params_arc);
+
+        let result = check_password_within_budget(&mut persistent_config, "password", at(1060));
+
+        assert_eq!(result, Ok(true));
+        let check_password_params = check_password_params_arc.lock().unwrap();
+        assert_eq!(*check_password_params, vec![Some("password".to_string())]);
+        let set_params = set_params_arc.lock().unwrap();
+        assert_eq!(*set_params, vec![(0, 0)]);
+    }
+
+    #[test]
+    fn database_errors_are_passed_along() {
+        let mut persistent_config = PersistentConfigurationMock::new()
+            .db_password_failures_result(Err(PersistentConfigError::NotPresent));
+
+        let result = check_password_within_budget(&mut persistent_config, "password", at(1000));
+
+        assert_eq!(result, Err(PersistentConfigError::NotPresent));
+    }
+}
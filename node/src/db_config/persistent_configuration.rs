@@ -8,7 +8,7 @@ use crate::db_config::typed_config_layer::{
 };
 use crate::sub_lib::cryptde::PlainData;
 use crate::sub_lib::neighborhood::NodeDescriptor;
-use crate::sub_lib::wallet::Wallet;
+use crate::sub_lib::wallet::{Wallet, WalletChange};
 use masq_lib::constants::{HIGHEST_USABLE_PORT, LOWEST_USABLE_INSECURE_PORT};
 use masq_lib::shared_schema::{ConfiguratorError, ParamError};
 use rustc_hex::ToHex;
@@ -26,6 +26,8 @@ pub enum PersistentConfigError {
     BadDerivationPathFormat(String),
     BadAddressFormat(String),
     Collision(String),
+    // Seconds until another db-password may be tried
+    PasswordCooldown(u64),
 }
 
 impl From<TypedConfigLayerError> for PersistentConfigError {
@@ -58,8 +60,15 @@ impl From<ConfigDaoError> for PersistentConfigError {
 
 impl PersistentConfigError {
     pub fn into_configurator_error(self, parameter: &str) -> ConfiguratorError {
+        let reason = match self {
+            PersistentConfigError::PasswordCooldown(seconds) => format!(
+                "Too many incorrect passwords; try again in {} seconds",
+                seconds
+            ),
+            e => format!("{:?}", e),
+        };
         ConfiguratorError {
-            param_errors: vec![ParamError::new(parameter, &format!("{:?}", self))],
+            param_errors: vec![ParamError::new(parameter, &reason)],
         }
     }
 }
@@ -72,6 +81,10 @@ pub trait PersistentConfiguration {
         old_password_opt: Option<&str>,
         new_password: &str,
     ) -> Result<(), PersistentConfigError>;
+    fn upgrade_password_protection(
+        &mut self,
+        db_password: &str,
+    ) -> Result<bool, PersistentConfigError>;
     fn clandestine_port(&self) -> Result<Option<u16>, PersistentConfigError>;
     fn set_clandestine_port(&mut self, port: u16) -> Result<(), PersistentConfigError>;
     fn gas_price(&self) -> Result<Option<u64>, PersistentConfigError>;
@@ -97,6 +110,13 @@ pub trait PersistentConfiguration {
     fn earning_wallet_from_address(&self) -> Result<Option<Wallet>, PersistentConfigError>;
     fn earning_wallet_address(&self) -> Result<Option<String>, PersistentConfigError>;
     fn set_earning_wallet_address(&mut self, address: &str) -> Result<(), PersistentConfigError>;
+    fn earning_wallet_change(&self) -> Result<Option<WalletChange>, PersistentConfigError>;
+    fn change_earning_wallet_address(
+        &mut self,
+        new_address: &str,
+        effective_block: u64,
+        signature: &str,
+    ) -> Result<(), PersistentConfigError>;
     fn past_neighbors(
         &self,
         db_password: &str,
@@ -106,8 +126,43 @@ pub trait PersistentConfiguration {
         node_descriptors_opt: Option<Vec<NodeDescriptor>>,
         db_password: &str,
     ) -> Result<(), PersistentConfigError>;
+    fn clandestine_keys(
+        &self,
+        db_password: &str,
+    ) -> Result<Option<PlainData>, PersistentConfigError>;
+    fn set_clandestine_keys(
+        &mut self,
+        keys_opt: Option<PlainData>,
+        db_password: &str,
+    ) -> Result<(), PersistentConfigError>;
+    fn split_tunnel_rules(&self) -> Result<Option<String>, PersistentConfigError>;
+    fn set_split_tunnel_rules(
+        &mut self,
+        rules_opt: Option<String>,
+    ) -> Result<(), PersistentConfigError>;
+    fn application_rules(&self) -> Result<Option<String>, PersistentConfigError>;
+    fn set_application_rules(
+        &mut self,
+        rules_opt: Option<String>,
+    ) -> Result<(), PersistentConfigError>;
     fn start_block(&self) -> Result<Option<u64>, PersistentConfigError>;
     fn set_start_block(&mut self, value: u64) -> Result<(), PersistentConfigError>;
+    fn log_rotation_size_mb(&self) -> Result<Option<u64>, PersistentConfigError>;
+    fn set_log_rotation_size_mb(&mut self, size_mb: u64) -> Result<(), PersistentConfigError>;
+    fn log_rotation_age_hours(&self) -> Result<Option<u64>, PersistentConfigError>;
+    fn set_log_rotation_age_hours(&mut self, age_hours: u64) -> Result<(), PersistentConfigError>;
+    fn log_retention(&self) -> Result<Option<u64>, PersistentConfigError>;
+    fn set_log_retention(&mut self, retention: u64) -> Result<(), PersistentConfigError>;
+    fn payment_token(&self) -> Result<Option<String>, PersistentConfigError>;
+    fn set_payment_token(&mut self, token: &str) -> Result<(), PersistentConfigError>;
+    // Incorrect db-passwords in a row, and the time (in seconds since the epoch) before which the
+    // next one mustn't be tried
+    fn db_password_failures(&self) -> Result<(u64, u64), PersistentConfigError>;
+    fn set_db_password_failures(
+        &mut self,
+        failures: u64,
+        retry_after: u64,
+    ) -> Result<(), PersistentConfigError>;
 }
 
 pub struct PersistentConfigurationReal {
@@ -144,6 +199,16 @@ impl PersistentConfiguration for PersistentConfigurationReal {
         Ok(writer.commit()?)
     }
 
+    fn upgrade_password_protection(
+        &mut self,
+        db_password: &str,
+    ) -> Result<bool, PersistentConfigError> {
+        let mut writer = self.dao.start_transaction()?;
+        let upgraded = self.scl.upgrade_encryption(db_password, &mut writer)?;
+        writer.commit()?;
+        Ok(upgraded)
+    }
+
     fn clandestine_port(&self) -> Result<Option<u16>, PersistentConfigError> {
         let unchecked_port = match decode_u64(self.dao.get("clandestine_port")?.value_opt)? {
             None => return Ok(None),
@@ -339,6 +404,59 @@ impl PersistentConfiguration for PersistentConfigurationReal {
         }
     }
 
+    // The wallet the Node earned into before the last deliberate change, the block from which the
+    // new one applies, and the previous wallet's signature of the change
+    fn earning_wallet_change(&self) -> Result<Option<WalletChange>, PersistentConfigError> {
+        let previous_address_opt = self.dao.get("previous_earning_wallet_address")?.value_opt;
+        let block_opt = decode_u64(self.dao.get("earning_wallet_change_block")?.value_opt)?;
+        let signature_opt = self.dao.get("earning_wallet_change_signature")?.value_opt;
+        match (previous_address_opt, block_opt, signature_opt) {
+            (Some(address), Some(block), Some(signature)) => match Wallet::from_str(&address) {
+                Ok(wallet) => Ok(Some(WalletChange {
+                    previous_wallet: wallet,
+                    effective_block: block,
+                    signature,
+                })),
+                Err(error) => panic!(
+                    "Database corrupt: invalid previous earning wallet address '{}': {:?}",
+                    address, error
+                ),
+            },
+            _ => Ok(None),
+        }
+    }
+
+    fn change_earning_wallet_address(
+        &mut self,
+        new_address: &str,
+        effective_block: u64,
+        signature: &str,
+    ) -> Result<(), PersistentConfigError> {
+        if Wallet::from_str(new_address).is_err() {
+            return Err(PersistentConfigError::BadAddressFormat(
+                new_address.to_string(),
+            ));
+        }
+        let mut writer = self.dao.start_transaction()?;
+        match writer.get("earning_wallet_address")?.value_opt {
+            None => writer.set("earning_wallet_address", Some(new_address.to_string()))?,
+            Some(existing_address) if new_address == existing_address => return Ok(()),
+            Some(existing_address) => {
+                writer.set("previous_earning_wallet_address", Some(existing_address))?;
+                writer.set(
+                    "earning_wallet_change_block",
+                    encode_u64(Some(effective_block))?,
+                )?;
+                writer.set(
+                    "earning_wallet_change_signature",
+                    Some(signature.to_string()),
+                )?;
+                writer.set("earning_wallet_address", Some(new_address.to_string()))?;
+            }
+        }
+        Ok(writer.commit()?)
+    }
+
     fn past_neighbors(
         &self,
         db_password: &str,
@@ -378,6 +496,61 @@ impl PersistentConfiguration for PersistentConfigurationReal {
         Ok(writer.commit()?)
     }
 
+    fn clandestine_keys(
+        &self,
+        db_password: &str,
+    ) -> Result<Option<PlainData>, PersistentConfigError> {
+        Ok(decode_bytes(self.scl.decrypt(
+            self.dao.get("clandestine_keys")?,
+            Some(db_password),
+            &self.dao,
+        )?)?)
+    }
+
+    fn set_clandestine_keys(
+        &mut self,
+        keys_opt: Option<PlainData>,
+        db_password: &str,
+    ) -> Result<(), PersistentConfigError> {
+        let mut writer = self.dao.start_transaction()?;
+        writer.set(
+            "clandestine_keys",
+            self.scl.encrypt(
+                "clandestine_keys",
+                encode_bytes(keys_opt)?,
+                Some(db_password),
+                &writer,
+            )?,
+        )?;
+        Ok(writer.commit()?)
+    }
+
+    fn split_tunnel_rules(&self) -> Result<Option<String>, PersistentConfigError> {
+        Ok(self.dao.get("split_tunnel_rules")?.value_opt)
+    }
+
+    fn application_rules(&self) -> Result<Option<String>, PersistentConfigError> {
+        Ok(self.dao.get("application_rules")?.value_opt)
+    }
+
+    fn set_application_rules(
+        &mut self,
+        rules_opt: Option<String>,
+    ) -> Result<(), PersistentConfigError> {
+        let mut writer = self.dao.start_transaction()?;
+        writer.set("application_rules", rules_opt)?;
+        Ok(writer.commit()?)
+    }
+
+    fn set_split_tunnel_rules(
+        &mut self,
+        rules_opt: Option<String>,
+    ) -> Result<(), PersistentConfigError> {
+        let mut writer = self.dao.start_transaction()?;
+        writer.set("split_tunnel_rules", rules_opt)?;
+        Ok(writer.commit()?)
+    }
+
     fn start_block(&self) -> Result<Option<u64>, PersistentConfigError> {
         Ok(decode_u64(self.dao.get("start_block")?.value_opt)?)
     }
@@ -387,6 +560,65 @@ impl PersistentConfiguration for PersistentConfigurationReal {
         writer.set("start_block", encode_u64(Some(value))?)?;
         Ok(writer.commit()?)
     }
+
+    fn log_rotation_size_mb(&self) -> Result<Option<u64>, PersistentConfigError> {
+        Ok(decode_u64(self.dao.get("log_rotation_size_mb")?.value_opt)?)
+    }
+
+    fn set_log_rotation_size_mb(&mut self, size_mb: u64) -> Result<(), PersistentConfigError> {
+        let mut writer = self.dao.start_transaction()?;
+        writer.set("log_rotation_size_mb", encode_u64(Some(size_mb))?)?;
+        Ok(writer.commit()?)
+    }
+
+    fn log_rotation_age_hours(&self) -> Result<Option<u64>, PersistentConfigError> {
+        Ok(decode_u64(
+            self.dao.get("log_rotation_age_hours")?.value_opt,
+        )?)
+    }
+
+    fn set_log_rotation_age_hours(&mut self, age_hours: u64) -> Result<(), PersistentConfigError> {
+        let mut writer = self.dao.start_transaction()?;
+        writer.set("log_rotation_age_hours", encode_u64(Some(age_hours))?)?;
+        Ok(writer.commit()?)
+    }
+
+    fn log_retention(&self) -> Result<Option<u64>, PersistentConfigError> {
+        Ok(decode_u64(self.dao.get("log_retention")?.value_opt)?)
+    }
+
+    fn set_log_retention(&mut self, retention: u64) -> Result<(), PersistentConfigError> {
+        let mut writer = self.dao.start_transaction()?;
+        writer.set("log_retention", encode_u64(Some(retention))?)?;
+        Ok(writer.commit()?)
+    }
+
+    fn payment_token(&self) -> Result<Option<String>, PersistentConfigError> {
+        Ok(self.dao.get("payment_token")?.value_opt)
+    }
+
+    fn set_payment_token(&mut self, token: &str) -> Result<(), PersistentConfigError> {
+        let mut writer = self.dao.start_transaction()?;
+        writer.set("payment_token", Some(token.to_string()))?;
+        Ok(writer.commit()?)
+    }
+
+    fn db_password_failures(&self) -> Result<(u64, u64), PersistentConfigError> {
+        let failures = decode_u64(self.dao.get("db_password_failures")?.value_opt)?;
+        let retry_after = decode_u64(self.dao.get("db_password_retry_after")?.value_opt)?;
+        Ok((failures.unwrap_or(0), retry_after.unwrap_or(0)))
+    }
+
+    fn set_db_password_failures(
+        &mut self,
+        failures: u64,
+        retry_after: u64,
+    ) -> Result<(), PersistentConfigError> {
+        let mut writer = self.dao.start_transaction()?;
+        writer.set("db_password_failures", encode_u64(Some(failures))?)?;
+        writer.set("db_password_retry_after", encode_u64(Some(retry_after))?)?;
+        Ok(writer.commit()?)
+    }
 }
 
 impl From<Box<dyn ConnectionWrapper>> for PersistentConfigurationReal {
@@ -543,6 +775,29 @@ mod tests {
         assert_eq!(*get_params, vec![EXAMPLE_ENCRYPTED.to_string()])
     }
 
+    #[test]
+    fn upgrade_password_protection_is_passed_through_to_secure_config_layer() {
+        let get_params_arc = Arc::new(Mutex::new(vec![]));
+        let commit_params_arc = Arc::new(Mutex::new(vec![]));
+        let writer = Box::new(
+            ConfigDaoWriteableMock::new()
+                .get_params(&get_params_arc)
+                .get_result(Ok(ConfigDaoRecord::new(EXAMPLE_ENCRYPTED, None, true)))
+                .commit_params(&commit_params_arc)
+                .commit_result(Ok(())),
+        );
+        let dao = Box::new(ConfigDaoMock::new().start_transaction_result(Ok(writer)));
+        let mut subject = PersistentConfigurationReal::new(dao);
+
+        let result = subject.upgrade_password_protection("password");
+
+        assert_eq!(result, Ok(false));
+        let get_params = get_params_arc.lock().unwrap();
+        assert_eq!(*get_params, vec![EXAMPLE_ENCRYPTED.to_string()]);
+        let commit_params = commit_params_arc.lock().unwrap();
+        assert_eq!(*commit_params, vec![()]);
+    }
+
     #[test]
     fn check_password_delegates_properly() {
         let get_string_params_arc = Arc::new(Mutex::new(vec![]));
@@ -745,6 +1000,217 @@ mod tests {
         assert_eq!(start_block, Some(6));
     }
 
+    #[test]
+    fn split_tunnel_rules_success() {
+        let get_params_arc = Arc::new(Mutex::new(vec![]));
+        let config_dao = Box::new(
+            ConfigDaoMock::new()
+                .get_params(&get_params_arc)
+                .get_result(Ok(ConfigDaoRecord::new(
+                    "split_tunnel_rules",
+                    Some("example.com,10.0.0.0/8"),
+                    false,
+                ))),
+        );
+        let subject = PersistentConfigurationReal::new(config_dao);
+
+        let result = subject.split_tunnel_rules().unwrap();
+
+        assert_eq!(result, Some("example.com,10.0.0.0/8".to_string()));
+        let get_params = get_params_arc.lock().unwrap();
+        assert_eq!(*get_params, vec!["split_tunnel_rules".to_string()]);
+    }
+
+    #[test]
+    fn set_split_tunnel_rules_success() {
+        let set_params_arc = Arc::new(Mutex::new(vec![]));
+        let writer = Box::new(
+            ConfigDaoWriteableMock::new()
+                .set_params(&set_params_arc)
+                .set_result(Ok(()))
+                .commit_result(Ok(())),
+        );
+        let config_dao = Box::new(ConfigDaoMock::new().start_transaction_result(Ok(writer)));
+        let mut subject = PersistentConfigurationReal::new(config_dao);
+
+        let result = subject.set_split_tunnel_rules(Some("example.com".to_string()));
+
+        assert_eq!(result, Ok(()));
+        let set_params = set_params_arc.lock().unwrap();
+        assert_eq!(
+            *set_params,
+            vec![(
+                "split_tunnel_rules".to_string(),
+                Some("example.com".to_string())
+            )]
+        )
+    }
+
+    #[test]
+    fn application_rules_success() {
+        let get_params_arc = Arc::new(Mutex::new(vec![]));
+        let config_dao = Box::new(
+            ConfigDaoMock::new()
+                .get_params(&get_params_arc)
+                .get_result(Ok(ConfigDaoRecord::new(
+                    "application_rules",
+                    Some("firefox:masq,curl:block"),
+                    false,
+                ))),
+        );
+        let subject = PersistentConfigurationReal::new(config_dao);
+
+        let result = subject.application_rules().unwrap();
+
+        assert_eq!(result, Some("firefox:masq,curl:block".to_string()));
+        let get_params = get_params_arc.lock().unwrap();
+        assert_eq!(*get_params, vec!["application_rules".to_string()]);
+    }
+
+    #[test]
+    fn set_application_rules_success() {
+        let set_params_arc = Arc::new(Mutex::new(vec![]));
+        let writer = Box::new(
+            ConfigDaoWriteableMock::new()
+                .set_params(&set_params_arc)
+                .set_result(Ok(()))
+                .commit_result(Ok(())),
+        );
+        let config_dao = Box::new(ConfigDaoMock::new().start_transaction_result(Ok(writer)));
+        let mut subject = PersistentConfigurationReal::new(config_dao);
+
+        let result = subject.set_application_rules(None);
+
+        assert_eq!(result, Ok(()));
+        let set_params = set_params_arc.lock().unwrap();
+        assert_eq!(*set_params, vec![("application_rules".to_string(), None)])
+    }
+
+    #[test]
+    fn payment_token_success() {
+        let get_params_arc = Arc::new(Mutex::new(vec![]));
+        let config_dao = Box::new(
+            ConfigDaoMock::new()
+                .get_params(&get_params_arc)
+                .get_result(Ok(ConfigDaoRecord::new(
+                    "payment_token",
+                    Some("USDC:0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48:6"),
+                    false,
+                ))),
+        );
+        let subject = PersistentConfigurationReal::new(config_dao);
+
+        let result = subject.payment_token().unwrap();
+
+        assert_eq!(
+            result,
+            Some("USDC:0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48:6".to_string())
+        );
+        let get_params = get_params_arc.lock().unwrap();
+        assert_eq!(*get_params, vec!["payment_token".to_string()]);
+    }
+
+    #[test]
+    fn set_payment_token_success() {
+        let set_params_arc = Arc::new(Mutex::new(vec![]));
+        let writer = Box::new(
+            ConfigDaoWriteableMock::new()
+                .set_params(&set_params_arc)
+                .set_result(Ok(()))
+                .commit_result(Ok(())),
+        );
+        let config_dao = Box::new(ConfigDaoMock::new().start_transaction_result(Ok(writer)));
+        let mut subject = PersistentConfigurationReal::new(config_dao);
+
+        let result =
+            subject.set_payment_token("MASQ:0x06f3c323f0238c72bf35011071f2b5b7f43a054c:18");
+
+        assert_eq!(result, Ok(()));
+        let set_params = set_params_arc.lock().unwrap();
+        assert_eq!(
+            *set_params,
+            vec![(
+                "payment_token".to_string(),
+                Some("MASQ:0x06f3c323f0238c72bf35011071f2b5b7f43a054c:18".to_string())
+            )]
+        )
+    }
+
+    #[test]
+    fn db_password_failures_success() {
+        let get_params_arc = Arc::new(Mutex::new(vec![]));
+        let config_dao = Box::new(
+            ConfigDaoMock::new()
+                .get_params(&get_params_arc)
+                .get_result(Ok(ConfigDaoRecord::new(
+                    "db_password_failures",
+                    Some("4"),
+                    false,
+                )))
+                .get_result(Ok(ConfigDaoRecord::new(
+                    "db_password_retry_after",
+                    None,
+                    false,
+                ))),
+        );
+        let subject = PersistentConfigurationReal::new(config_dao);
+
+        let result = subject.db_password_failures();
+
+        assert_eq!(result, Ok((4, 0)));
+        let get_params = get_params_arc.lock().unwrap();
+        assert_eq!(
+            *get_params,
+            vec![
+                "db_password_failures".to_string(),
+                "db_password_retry_after".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn set_db_password_failures_success() {
+        let set_params_arc = Arc::new(Mutex::new(vec![]));
+        let writer = Box::new(
+            ConfigDaoWriteableMock::new()
+                .set_params(&set_params_arc)
+                .set_result(Ok(()))
+                .set_result(Ok(()))
+                .commit_result(Ok(())),
+        );
+        let config_dao = Box::new(ConfigDaoMock::new().start_transaction_result(Ok(writer)));
+        let mut subject = PersistentConfigurationReal::new(config_dao);
+
+        let result = subject.set_db_password_failures(5, 1_600_000_030);
+
+        assert_eq!(result, Ok(()));
+        let set_params = set_params_arc.lock().unwrap();
+        assert_eq!(
+            *set_params,
+            vec![
+                ("db_password_failures".to_string(), Some("5".to_string())),
+                (
+                    "db_password_retry_after".to_string(),
+                    Some("1600000030".to_string())
+                )
+            ]
+        )
+    }
+
+    #[test]
+    fn a_password_cooldown_tells_the_user_how_long_to_wait() {
+        let result =
+            PersistentConfigError::PasswordCooldown(120).into_configurator_error("db-password");
+
+        assert_eq!(
+            result,
+            ConfiguratorError::required(
+                "db-password",
+                "Too many incorrect passwords; try again in 120 seconds"
+            )
+        );
+    }
+
     #[test]
     fn set_start_block_success() {
         let set_params_arc = Arc::new(Mutex::new(vec![]));
@@ -805,6 +1271,70 @@ mod tests {
         )
     }
 
+    #[test]
+    fn log_rotation_settings() {
+        let config_dao = Box::new(
+            ConfigDaoMock::new()
+                .get_result(Ok(ConfigDaoRecord::new(
+                    "log_rotation_size_mb",
+                    Some("20"),
+                    false,
+                )))
+                .get_result(Ok(ConfigDaoRecord::new(
+                    "log_rotation_age_hours",
+                    Some("24"),
+                    false,
+                )))
+                .get_result(Ok(ConfigDaoRecord::new("log_retention", None, false))),
+        );
+        let subject = PersistentConfigurationReal::new(config_dao);
+
+        let size_mb = subject.log_rotation_size_mb().unwrap();
+        let age_hours = subject.log_rotation_age_hours().unwrap();
+        let retention = subject.log_retention().unwrap();
+
+        assert_eq!(size_mb, Some(20));
+        assert_eq!(age_hours, Some(24));
+        assert_eq!(retention, None);
+    }
+
+    #[test]
+    fn set_log_rotation_settings_succeeds() {
+        let set_params_arc = Arc::new(Mutex::new(vec![]));
+        let make_writer = || {
+            Box::new(
+                ConfigDaoWriteableMock::new()
+                    .set_params(&set_params_arc)
+                    .set_result(Ok(()))
+                    .commit_result(Ok(())),
+            )
+        };
+        let config_dao = Box::new(
+            ConfigDaoMock::new()
+                .start_transaction_result(Ok(make_writer()))
+                .start_transaction_result(Ok(make_writer()))
+                .start_transaction_result(Ok(make_writer())),
+        );
+        let mut subject = PersistentConfigurationReal::new(config_dao);
+
+        let size_result = subject.set_log_rotation_size_mb(20);
+        let age_result = subject.set_log_rotation_age_hours(24);
+        let retention_result = subject.set_log_retention(7);
+
+        assert_eq!(size_result, Ok(()));
+        assert_eq!(age_result, Ok(()));
+        assert_eq!(retention_result, Ok(()));
+        let set_params = set_params_arc.lock().unwrap();
+        assert_eq!(
+            *set_params,
+            vec![
+                ("log_rotation_size_mb".to_string(), Some("20".to_string())),
+                ("log_rotation_age_hours".to_string(), Some("24".to_string())),
+                ("log_retention".to_string(), Some("7".to_string())),
+            ]
+        )
+    }
+
     #[test]
     fn past_neighbors_success() {
         let example = "Aside from that, Mrs. Lincoln, how was the play?".as_bytes();
@@ -895,6 +1425,83 @@ mod tests {
         assert_eq!(set_params.len(), 1);
     }
 
+    #[test]
+    fn clandestine_keys_success() {
+        let example = "Aside from that, Mrs. Lincoln, how was the play?".as_bytes();
+        let example_encrypted = Bip39::encrypt_bytes(&example, "password").unwrap();
+        let keys = PlainData::new(&[1, 2, 3, 4]);
+        let keys_string = encode_bytes(Some(keys.clone())).unwrap().unwrap();
+        let keys_enc = Bip39::encrypt_bytes(&keys_string.as_bytes(), "password").unwrap();
+        let get_params_arc = Arc::new(Mutex::new(vec![]));
+        let config_dao = Box::new(
+            ConfigDaoMock::new()
+                .get_params(&get_params_arc)
+                .get_result(Ok(ConfigDaoRecord::new(
+                    "clandestine_keys",
+                    Some(&keys_enc),
+                    true,
+                )))
+                .get_result(Ok(ConfigDaoRecord::new(
+                    EXAMPLE_ENCRYPTED,
+                    Some(&example_encrypted),
+                    true,
+                ))),
+        );
+        let subject = PersistentConfigurationReal::new(config_dao);
+
+        let result = subject.clandestine_keys("password").unwrap();
+
+        assert_eq!(result, Some(keys));
+        let get_params = get_params_arc.lock().unwrap();
+        assert_eq!(
+            *get_params,
+            vec![
+                "clandestine_keys".to_string(),
+                EXAMPLE_ENCRYPTED.to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn set_clandestine_keys_success() {
+        let example = "Aside from that, Mrs. Lincoln, how was the play?".as_bytes();
+        let example_encrypted = Bip39::encrypt_bytes(&example, "password").unwrap();
+        let keys = PlainData::new(&[1, 2, 3, 4]);
+        let set_params_arc = Arc::new(Mutex::new(vec![]));
+        let writer = Box::new(
+            ConfigDaoWriteableMock::new()
+                .get_result(Ok(ConfigDaoRecord::new(
+                    EXAMPLE_ENCRYPTED,
+                    Some(&example_encrypted),
+                    true,
+                )))
+                .get_result(Ok(ConfigDaoRecord::new(
+                    "clandestine_keys",
+                    Some("irrelevant"),
+                    true,
+                )))
+                .set_params(&set_params_arc)
+                .set_result(Ok(()))
+                .commit_result(Ok(())),
+        );
+        let config_dao = Box::new(ConfigDaoMock::new().start_transaction_result(Ok(writer)));
+        let mut subject = PersistentConfigurationReal::new(config_dao);
+
+        subject
+            .set_clandestine_keys(Some(keys.clone()), "password")
+            .unwrap();
+
+        let set_params = set_params_arc.lock().unwrap();
+        assert_eq!(set_params[0].0, "clandestine_keys".to_string());
+        let encrypted_keys = set_params[0].1.clone().unwrap();
+        let encoded_keys = Bip39::decrypt_bytes(&encrypted_keys, "password").unwrap();
+        let actual_keys = decode_bytes(Some(String::from_utf8(encoded_keys.into()).unwrap()))
+            .unwrap()
+            .unwrap();
+        assert_eq!(actual_keys, keys);
+        assert_eq!(set_params.len(), 1);
+    }
+
     #[test]
     fn consuming_wallet_public_key_retrieves_existing_key() {
         let get_params_arc = Arc::new(Mutex::new(vec![]));
@@ -1765,4 +2372,169 @@ mod tests {
             ))
         );
     }
+
+    #[test]
+    fn earning_wallet_change_is_none_if_the_wallet_never_changed() {
+        let config_dao = ConfigDaoMock::new()
+            .get_result(Ok(ConfigDaoRecord::new(
+                "previous_earning_wallet_address",
+                None,
+                false,
+            )))
+            .get_result(Ok(ConfigDaoRecord::new(
+                "earning_wallet_change_block",
+                None,
+                false,
+            )))
+            .get_result(Ok(ConfigDaoRecord::new(
+                "earning_wallet_change_signature",
+                None,
+                false,
+            )));
+        let subject = PersistentConfigurationReal::new(Box::new(config_dao));
+
+        let result = subject.earning_wallet_change();
+
+        assert_eq!(result, Ok(None));
+    }
+
+    #[test]
+    fn earning_wallet_change_reports_previous_wallet_effective_block_and_signature() {
+        let get_params_arc = Arc::new(Mutex::new(vec![]));
+        let config_dao = ConfigDaoMock::new()
+            .get_params(&get_params_arc)
+            .get_result(Ok(ConfigDaoRecord::new(
+                "previous_earning_wallet_address",
+                Some("0x8e6dabd6b5c75291a3258c29b418f5805792a886"),
+                false,
+            )))
+            .get_result(Ok(ConfigDaoRecord::new(
+                "earning_wallet_change_block",
+                Some("1234"),
+                false,
+            )))
+            .get_result(Ok(ConfigDaoRecord::new(
+                "earning_wallet_change_signature",
+                Some("0x0102"),
+                false,
+            )));
+        let subject = PersistentConfigurationReal::new(Box::new(config_dao));
+
+        let result = subject.earning_wallet_change();
+
+        assert_eq!(
+            result,
+            Ok(Some(WalletChange {
+                previous_wallet: Wallet::from_str("0x8e6dabd6b5c75291a3258c29b418f5805792a886")
+                    .unwrap(),
+                effective_block: 1234,
+                signature: "0x0102".to_string()
+            }))
+        );
+        let get_params = get_params_arc.lock().unwrap();
+        assert_eq!(
+            *get_params,
+            vec![
+                "previous_earning_wallet_address".to_string(),
+                "earning_wallet_change_block".to_string(),
+                "earning_wallet_change_signature".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn change_earning_wallet_address_remembers_the_previous_address() {
+        let set_params_arc = Arc::new(Mutex::new(vec![]));
+        let commit_params_arc = Arc::new(Mutex::new(vec![]));
+        let writer = Box::new(
+            ConfigDaoWriteableMock::new()
+                .get_result(Ok(ConfigDaoRecord::new(
+                    "earning_wallet_address",
+                    Some("0x8e6dabd6b5c75291a3258c29b418f5805792a886"),
+                    false,
+                )))
+                .set_params(&set_params_arc)
+                .set_result(Ok(()))
+                .set_result(Ok(()))
+                .set_result(Ok(()))
+                .set_result(Ok(()))
+                .commit_params(&commit_params_arc)
+                .commit_result(Ok(())),
+        );
+        let config_dao = Box::new(ConfigDaoMock::new().start_transaction_result(Ok(writer)));
+        let mut subject = PersistentConfigurationReal::new(config_dao);
+
+        let result = subject.change_earning_wallet_address(
+            "0x7d6dabd6b5c75291a3258c29b418f5805792a875",
+            1234,
+            "0x0102",
+        );
+
+        assert_eq!(result, Ok(()));
+        let set_params = set_params_arc.lock().unwrap();
+        assert_eq!(
+            *set_params,
+            vec![
+                (
+                    "previous_earning_wallet_address".to_string(),
+                    Some("0x8e6dabd6b5c75291a3258c29b418f5805792a886".to_string())
+                ),
+                (
+                    "earning_wallet_change_block".to_string(),
+                    Some("1234".to_string())
+                ),
+                (
+                    "earning_wallet_change_signature".to_string(),
+                    Some("0x0102".to_string())
+                ),
+                (
+                    "earning_wallet_address".to_string(),
+                    Some("0x7d6dabd6b5c75291a3258c29b418f5805792a875".to_string())
+                ),
+            ]
+        );
+        let commit_params = commit_params_arc.lock().unwrap();
+        assert_eq!(*commit_params, vec![()]);
+    }
+
+    #[test]
+    fn change_earning_wallet_address_does_nothing_if_the_address_is_unchanged() {
+        let set_params_arc = Arc::new(Mutex::new(vec![]));
+        let writer = Box::new(
+            ConfigDaoWriteableMock::new()
+                .get_result(Ok(ConfigDaoRecord::new(
+                    "earning_wallet_address",
+                    Some("0x7d6dabd6b5c75291a3258c29b418f5805792a875"),
+                    false,
+                )))
+                .set_params(&set_params_arc),
+        );
+        let config_dao = Box::new(ConfigDaoMock::new().start_transaction_result(Ok(writer)));
+        let mut subject = PersistentConfigurationReal::new(config_dao);
+
+        let result = subject.change_earning_wallet_address(
+            "0x7d6dabd6b5c75291a3258c29b418f5805792a875",
+            1234,
+            "0x0102",
+        );
+
+        assert_eq!(result, Ok(()));
+        let set_params = set_params_arc.lock().unwrap();
+        assert!(set_params.is_empty());
+    }
+
+    #[test]
+    fn change_earning_wallet_address_complains_if_new_address_is_invalid() {
+        let config_dao = Box::new(ConfigDaoMock::new());
+        let mut subject = PersistentConfigurationReal::new(config_dao);
+
+        let result = subject.change_earning_wallet_address("invalid address", 1234, "0x0102");
+
+        assert_eq!(
+            result,
+            Err(PersistentConfigError::BadAddressFormat(
+                "invalid address".to_string()
+            ))
+        );
+    }
 }
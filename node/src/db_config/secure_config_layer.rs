@@ -64,6 +64,31 @@ impl SecureConfigLayer {
         self.install_example_for_password(new_password, dao)?;
         Ok(())
     }
+
+    // Values encrypted by an older Node, or with weaker key-derivation parameters than this one
+    // uses, are encrypted again with the current ones. Returns true if anything was rewritten.
+    pub fn upgrade_encryption<'b, T: ConfigDaoReadWrite + ?Sized>(
+        &self,
+        db_password: &str,
+        dao: &'b mut Box<T>,
+    ) -> Result<bool, SecureConfigLayerError> {
+        if !self.check_password(Some(db_password), dao)? {
+            return Ok(false);
+        }
+        let needs_upgrade =
+            dao.get_all()?
+                .into_iter()
+                .any(|record| match (record.encrypted, &record.value_opt) {
+                    (true, Some(value)) => Bip39::needs_reencryption(value),
+                    _ => false,
+                });
+        if !needs_upgrade {
+            return Ok(false);
+        }
+        self.reencrypt_records(Some(db_password), db_password, dao)?;
+        self.install_example_for_password(db_password, dao)?;
+        Ok(true)
+    }
     #[allow(clippy::borrowed_box)]
     pub fn encrypt<T: ConfigDaoRead + ?Sized>(
         &self,
@@ -239,8 +264,23 @@ mod tests {
     use crate::db_config::mocks::{ConfigDaoMock, ConfigDaoWriteableMock};
     use crate::db_config::secure_config_layer::SecureConfigLayerError::DatabaseError;
     use crate::sub_lib::cryptde::PlainData;
+    use ethsign::keyfile::Crypto;
+    use ethsign::Protected;
+    use rustc_hex::ToHex;
+    use std::num::NonZeroU32;
     use std::sync::{Arc, Mutex};
 
+    // The way values were encrypted before Argon2id
+    fn encrypt_pbkdf2_bytes(value: &[u8], db_password: &str) -> String {
+        let crypto = Crypto::encrypt(
+            value,
+            &Protected::new(db_password.as_bytes()),
+            NonZeroU32::new(10240).unwrap(),
+        )
+        .unwrap();
+        serde_cbor::to_vec(&crypto).unwrap().to_hex()
+    }
+
     #[test]
     fn secure_config_layer_error_from_config_dao_error() {
         assert_eq!(
@@ -532,6 +572,95 @@ mod tests {
         assert_eq!(result, Err(SecureConfigLayerError::PasswordError));
     }
 
+    #[test]
+    fn upgrade_encryption_reencrypts_values_encrypted_by_older_nodes() {
+        let set_params_arc = Arc::new(Mutex::new(vec![]));
+        let example = "Aside from that, Mrs. Lincoln, how was the play?".as_bytes();
+        let encrypted_example = encrypt_pbkdf2_bytes(&example, "password");
+        let unencrypted_value = "These are the times that try men's souls.".as_bytes();
+        let old_encrypted_value = encrypt_pbkdf2_bytes(&unencrypted_value, "password");
+        let all_records = vec![
+            ConfigDaoRecord::new(EXAMPLE_ENCRYPTED, Some(&encrypted_example), true),
+            ConfigDaoRecord::new("unencrypted_value_key", Some("unencrypted_value"), false),
+            ConfigDaoRecord::new("encrypted_value_key", Some(&old_encrypted_value), true),
+        ];
+        let mut writeable = Box::new(
+            ConfigDaoWriteableMock::new()
+                .get_result(Ok(ConfigDaoRecord::new(
+                    EXAMPLE_ENCRYPTED,
+                    Some(&encrypted_example),
+                    true,
+                )))
+                .get_all_result(Ok(all_records.clone()))
+                .get_all_result(Ok(all_records))
+                .set_params(&set_params_arc)
+                .set_result(Ok(()))
+                .set_result(Ok(()))
+                .set_result(Ok(())),
+        );
+        let subject = SecureConfigLayer::new();
+
+        let result = subject.upgrade_encryption("password", &mut writeable);
+
+        assert_eq!(result, Ok(true));
+        let set_params = set_params_arc.lock().unwrap();
+        assert_eq!(set_params.len(), 3);
+        assert_eq!(set_params[1].0, "encrypted_value_key".to_string());
+        let new_encrypted_value = set_params[1].1.as_ref().unwrap();
+        assert_eq!(Bip39::needs_reencryption(new_encrypted_value), false);
+        assert_eq!(
+            Bip39::decrypt_bytes(new_encrypted_value, "password").unwrap(),
+            PlainData::new(unencrypted_value)
+        );
+        assert_eq!(set_params[2].0, EXAMPLE_ENCRYPTED.to_string());
+        assert_eq!(
+            Bip39::needs_reencryption(set_params[2].1.as_ref().unwrap()),
+            false
+        );
+    }
+
+    #[test]
+    fn upgrade_encryption_leaves_current_values_alone() {
+        let set_params_arc = Arc::new(Mutex::new(vec![]));
+        let example = "Aside from that, Mrs. Lincoln, how was the play?".as_bytes();
+        let encrypted_example = Bip39::encrypt_bytes(&example, "password").unwrap();
+        let encrypted_value = Bip39::encrypt_bytes(&example, "password").unwrap();
+        let mut writeable = Box::new(
+            ConfigDaoWriteableMock::new()
+                .get_result(Ok(ConfigDaoRecord::new(
+                    EXAMPLE_ENCRYPTED,
+                    Some(&encrypted_example),
+                    true,
+                )))
+                .get_all_result(Ok(vec![
+                    ConfigDaoRecord::new(EXAMPLE_ENCRYPTED, Some(&encrypted_example), true),
+                    ConfigDaoRecord::new("encrypted_value_key", Some(&encrypted_value), true),
+                ]))
+                .set_params(&set_params_arc),
+        );
+        let subject = SecureConfigLayer::new();
+
+        let result = subject.upgrade_encryption("password", &mut writeable);
+
+        assert_eq!(result, Ok(false));
+        let set_params = set_params_arc.lock().unwrap();
+        assert_eq!(*set_params, vec![]);
+    }
+
+    #[test]
+    fn upgrade_encryption_does_nothing_with_the_wrong_password() {
+        let example = "Aside from that, Mrs. Lincoln, how was the play?".as_bytes();
+        let encrypted_example = encrypt_pbkdf2_bytes(&example, "password");
+        let mut writeable = Box::new(ConfigDaoWriteableMock::new().get_result(Ok(
+            ConfigDaoRecord::new(EXAMPLE_ENCRYPTED, Some(&encrypted_example), true),
+        )));
+        let subject = SecureConfigLayer::new();
+
+        let result = subject.upgrade_encryption("drowssap", &mut writeable);
+
+        assert_eq!(result, Ok(false));
+    }
+
     #[test]
     #[should_panic(
         expected = "Database is corrupt: configuration value 'badly_encrypted' cannot be decrypted"
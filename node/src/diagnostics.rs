@@ -0,0 +1,353 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+
+use crate::database::config_dumper::{distill_args, redacted_config};
+use crate::node_configurator::RealDirsWrapper;
+use crate::privilege_drop::{PrivilegeDropper, PrivilegeDropperReal};
+use crate::sub_lib::cli_output;
+use crate::sub_lib::cli_output::OutputFormat;
+use crate::sub_lib::log_rotation::recent_archives;
+use chrono::Local;
+use clap::Arg;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use masq_lib::command::StdStreams;
+use masq_lib::constants::CURRENT_LOGFILE_NAME;
+use masq_lib::shared_schema::ConfiguratorError;
+use serde_json::json;
+use std::fs::File;
+use std::io;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tar::{Builder, Header};
+
+const COLLECT_DIAGNOSTICS_HELP: &str =
+    "Package recent logs, the configuration with its secrets redacted, and version and platform \
+     information into a single file in the data directory that you can attach to a bug report. \
+     Look it over before you post it: logs can contain IP addresses and host names.";
+
+const BUNDLE_PREFIX: &str = "MASQNode_diagnostics_";
+const BUNDLE_SUFFIX: &str = ".tar.gz";
+const RECENT_ARCHIVE_COUNT: usize = 3;
+// The end of a log is where the trouble usually is
+const MAX_CURRENT_LOG_SIZE: u64 = 20_000_000;
+
+pub fn collect_diagnostics(
+    args: &[String],
+    streams: &mut StdStreams,
+) -> Result<i32, ConfiguratorError> {
+    let mode_arg = Arg::with_name("collect-diagnostics")
+        .long("collect-diagnostics")
+        .required(true)
+        .takes_value(false)
+        .help(COLLECT_DIAGNOSTICS_HELP);
+    let (real_user, data_directory, chain_id) =
+        distill_args(&RealDirsWrapper {}, mode_arg, args, streams)?;
+    PrivilegeDropperReal::new().drop_privileges(&real_user);
+    let output_format = OutputFormat::from_args(args);
+    match make_bundle(&data_directory, chain_id, None).write(&data_directory) {
+        Ok(bundle_path) => {
+            match output_format {
+                OutputFormat::Human => {
+                    writeln!(streams.stdout, "{}", bundle_path.display()).expect("writeln! failed")
+                }
+                OutputFormat::Json => cli_output::write(
+                    streams.stdout,
+                    &cli_output::success(
+                        "collectDiagnostics",
+                        json!({ "bundle": bundle_path.to_string_lossy() }),
+                    ),
+                ),
+            }
+            Ok(0)
+        }
+        Err(e) => {
+            let message = format!(
+                "Couldn't write diagnostics into {}: {}",
+                data_directory.display(),
+                e
+            );
+            match output_format {
+                OutputFormat::Human => {
+                    writeln!(streams.stderr, "{}", message).expect("writeln! failed")
+                }
+                OutputFormat::Json => cli_output::write(
+                    streams.stdout,
+                    &cli_output::failure("collectDiagnostics", "io", &message, None),
+                ),
+            }
+            Ok(1)
+        }
+    }
+}
+
+// Only a running Node can say what its neighborhood looks like
+pub fn make_bundle(
+    data_directory: &Path,
+    chain_id: u8,
+    neighborhood_opt: Option<String>,
+) -> DiagnosticsBundle {
+    let mut bundle = DiagnosticsBundle::new();
+    bundle.add_bytes("about.json", about(chain_id).into_bytes());
+    match redacted_config(&data_directory.to_path_buf(), chain_id) {
+        Ok(config) => bundle.add_bytes("config.json", config.into_bytes()),
+        Err(e) => bundle.add_bytes("config.txt", e.into_bytes()),
+    }
+    match neighborhood_opt {
+        Some(neighborhood) => bundle.add_bytes("neighborhood.json", neighborhood.into_bytes()),
+        None => bundle.add_bytes(
+            "neighborhood.txt",
+            b"The Node wasn't running, so there's no neighborhood snapshot".to_vec(),
+        ),
+    }
+    if let Ok(current_log) = tail(&data_directory.join(CURRENT_LOGFILE_NAME)) {
+        bundle.add_bytes(&format!("logs/{}", CURRENT_LOGFILE_NAME), current_log);
+    }
+    recent_archives(data_directory, RECENT_ARCHIVE_COUNT)
+        .unwrap_or_default()
+        .into_iter()
+        .for_each(|archive| bundle.add_file(archive));
+    bundle
+}
+
+enum BundleContents {
+    Bytes(Vec<u8>),
+    File(PathBuf),
+}
+
+pub struct DiagnosticsBundle {
+    entries: Vec<(String, BundleContents)>,
+}
+
+impl Default for DiagnosticsBundle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DiagnosticsBundle {
+    pub fn new() -> DiagnosticsBundle {
+        DiagnosticsBundle { entries: vec![] }
+    }
+
+    pub fn add_bytes(&mut self, name: &str, contents: Vec<u8>) {
+        self.entries
+            .push((name.to_string(), BundleContents::Bytes(contents)))
+    }
+
+    // Goes under logs/, by the name it already has
+    pub fn add_file(&mut self, path: PathBuf) {
+        let name = format!(
+            "logs/{}",
+            path.file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default()
+        );
+        self.entries.push((name, BundleContents::File(path)))
+    }
+
+    // Returns the tarball, named for when it was made
+    pub fn write(&self, directory: &Path) -> io::Result<PathBuf> {
+        let path = directory.join(format!(
+            "{}{}{}",
+            BUNDLE_PREFIX,
+            Local::now().format("%Y-%m-%dT%H-%M-%S%.3f"),
+            BUNDLE_SUFFIX
+        ));
+        let mut builder =
+            Builder::new(GzEncoder::new(File::create(&path)?, Compression::default()));
+        for (name, contents) in &self.entries {
+            match contents {
+                BundleContents::Bytes(bytes) => {
+                    let mut header = Header::new_gnu();
+                    header.set_size(bytes.len() as u64);
+                    header.set_mode(0o644);
+                    header.set_mtime(
+                        SystemTime::now()
+                            .duration_since(UNIX_EPOCH)
+                            .map(|since| since.as_secs())
+                            .unwrap_or(0),
+                    );
+                    header.set_cksum();
+                    builder.append_data(&mut header, name, bytes.as_slice())?
+                }
+                // It may have been pruned since it was listed; that's no reason to give up
+                BundleContents::File(file_path) => {
+                    if let Ok(mut file) = File::open(file_path) {
+                        builder.append_file(name, &mut file)?
+                    }
+                }
+            }
+        }
+        builder.into_inner()?.finish()?;
+        Ok(path)
+    }
+}
+
+fn about(chain_id: u8) -> String {
+    serde_json::to_string_pretty(&json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "os": std::env::consts::OS,
+        "arch": std::env::consts::ARCH,
+        "family": std::env::consts::FAMILY,
+        "chainId": chain_id,
+        "collectedAt": Local::now().to_rfc3339(),
+    }))
+    .expect("Couldn't serialize version information")
+}
+
+fn tail(path: &Path) -> io::Result<Vec<u8>> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    file.seek(SeekFrom::Start(len.saturating_sub(MAX_CURRENT_LOG_SIZE)))?;
+    let mut contents = vec![];
+    file.read_to_end(&mut contents)?;
+    Ok(contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::db_initializer::{DbInitializer, DbInitializerReal};
+    use crate::test_utils::ArgsBuilder;
+    use flate2::read::GzDecoder;
+    use masq_lib::test_utils::fake_stream_holder::FakeStreamHolder;
+    use masq_lib::test_utils::utils::{
+        ensure_node_home_directory_exists, DEFAULT_CHAIN_ID, TEST_DEFAULT_CHAIN_NAME,
+    };
+    use std::collections::HashMap;
+    use std::fs;
+    use tar::Archive;
+
+    fn unpack(bundle_path: &Path) -> HashMap<String, Vec<u8>> {
+        let mut archive = Archive::new(GzDecoder::new(File::open(bundle_path).unwrap()));
+        archive
+            .entries()
+            .unwrap()
+            .map(|entry_result| {
+                let mut entry = entry_result.unwrap();
+                let name = entry.path().unwrap().to_string_lossy().to_string();
+                let mut contents = vec![];
+                entry.read_to_end(&mut contents).unwrap();
+                (name, contents)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn bundle_holds_what_a_bug_report_needs() {
+        let data_dir = ensure_node_home_directory_exists(
+            "diagnostics",
+            "bundle_holds_what_a_bug_report_needs",
+        );
+        DbInitializerReal::new()
+            .initialize(&data_dir, DEFAULT_CHAIN_ID, true)
+            .unwrap();
+        fs::write(data_dir.join(CURRENT_LOGFILE_NAME), "Something went wrong").unwrap();
+        fs::write(
+            data_dir.join("MASQNode_2020-01-01T00-00-00.000.log.gz"),
+            "archive",
+        )
+        .unwrap();
+
+        let bundle_path = make_bundle(&data_dir, DEFAULT_CHAIN_ID, Some("{}".to_string()))
+            .write(&data_dir)
+            .unwrap();
+
+        let file_name = bundle_path.file_name().unwrap().to_str().unwrap();
+        assert_eq!(file_name.starts_with(BUNDLE_PREFIX), true, "{}", file_name);
+        assert_eq!(file_name.ends_with(BUNDLE_SUFFIX), true, "{}", file_name);
+        let entries = unpack(&bundle_path);
+        let mut names = entries.keys().cloned().collect::<Vec<String>>();
+        names.sort();
+        assert_eq!(
+            names,
+            vec![
+                "about.json",
+                "config.json",
+                "logs/MASQNode_2020-01-01T00-00-00.000.log.gz",
+                "logs/MASQNode_rCURRENT.log",
+                "neighborhood.json",
+            ]
+        );
+        let about: serde_json::Value = serde_json::from_slice(&entries["about.json"]).unwrap();
+        assert_eq!(about["version"], json!(env!("CARGO_PKG_VERSION")));
+        assert_eq!(about["os"], json!(std::env::consts::OS));
+        assert_eq!(
+            entries["logs/MASQNode_rCURRENT.log"],
+            b"Something went wrong".to_vec()
+        );
+        assert_eq!(entries["neighborhood.json"], b"{}".to_vec());
+    }
+
+    #[test]
+    fn bundle_without_a_database_or_a_neighborhood_says_so() {
+        let data_dir = ensure_node_home_directory_exists(
+            "diagnostics",
+            "bundle_without_a_database_or_a_neighborhood_says_so",
+        );
+
+        let bundle_path = make_bundle(&data_dir, DEFAULT_CHAIN_ID, None)
+            .write(&data_dir)
+            .unwrap();
+
+        let entries = unpack(&bundle_path);
+        assert_eq!(
+            String::from_utf8(entries["config.txt"].clone())
+                .unwrap()
+                .starts_with("Can't open database: "),
+            true
+        );
+        assert_eq!(
+            entries["neighborhood.txt"],
+            b"The Node wasn't running, so there's no neighborhood snapshot".to_vec()
+        );
+        assert_eq!(data_dir.join("node-data.db").exists(), false);
+    }
+
+    #[test]
+    fn collect_diagnostics_names_the_bundle_on_stdout() {
+        let data_dir = ensure_node_home_directory_exists(
+            "diagnostics",
+            "collect_diagnostics_names_the_bundle_on_stdout",
+        );
+        let mut holder = FakeStreamHolder::new();
+        let args_vec: Vec<String> = ArgsBuilder::new()
+            .param("--data-directory", data_dir.to_str().unwrap())
+            .param("--real-user", "123::")
+            .param("--chain", TEST_DEFAULT_CHAIN_NAME)
+            .opt("--collect-diagnostics")
+            .into();
+
+        let result = collect_diagnostics(args_vec.as_slice(), &mut holder.streams()).unwrap();
+
+        assert_eq!(result, 0);
+        let bundle_path = PathBuf::from(holder.stdout.get_string().trim_end());
+        assert_eq!(bundle_path.parent().unwrap(), data_dir.as_path());
+        assert_eq!(unpack(&bundle_path).contains_key("about.json"), true);
+    }
+
+    #[test]
+    fn collect_diagnostics_names_the_bundle_in_json_when_asked() {
+        let data_dir = ensure_node_home_directory_exists(
+            "diagnostics",
+            "collect_diagnostics_names_the_bundle_in_json_when_asked",
+        );
+        let mut holder = FakeStreamHolder::new();
+        let args_vec: Vec<String> = ArgsBuilder::new()
+            .param("--data-directory", data_dir.to_str().unwrap())
+            .param("--real-user", "123::")
+            .param("--chain", TEST_DEFAULT_CHAIN_NAME)
+            .param("--output", "json")
+            .opt("--collect-diagnostics")
+            .into();
+
+        let result = collect_diagnostics(args_vec.as_slice(), &mut holder.streams()).unwrap();
+
+        assert_eq!(result, 0);
+        let output: serde_json::Value = serde_json::from_str(&holder.stdout.get_string()).unwrap();
+        assert_eq!(output["mode"], json!("collectDiagnostics"));
+        let bundle_path = PathBuf::from(output["result"]["bundle"].as_str().unwrap());
+        assert_eq!(bundle_path.parent().unwrap(), data_dir.as_path());
+    }
+}
@@ -2,20 +2,28 @@
 use crate::stream_messages::{PoolBindMessage, RemovedStreamType};
 use crate::sub_lib::dispatcher::InboundClientData;
 use crate::sub_lib::dispatcher::{DispatcherSubs, StreamShutdownMsg};
+use crate::sub_lib::log_control::{change_log_levels, parse_level, LogLevels};
+use crate::sub_lib::log_rotation::rotate_log_now;
 use crate::sub_lib::logger::Logger;
 use crate::sub_lib::peer_actors::BindMessage;
-use crate::sub_lib::stream_handler_pool::TransmitDataMsg;
+use crate::sub_lib::stream_handler_pool::{PunchHoleMsg, TransmitDataMsg};
 use crate::sub_lib::utils::{handle_ui_crash_request, NODE_MAILBOX_CAPACITY};
 use actix::Actor;
 use actix::Addr;
 use actix::Context;
 use actix::Handler;
 use actix::Recipient;
+use log::LevelFilter;
 use masq_lib::crash_point::CrashPoint;
 use masq_lib::messages::{
     FromMessageBody, ToMessageBody, UiCrashRequest, UiDescriptorRequest, UiDescriptorResponse,
+    UiLogLevelRequest, UiLogLevelResponse, UiRotateLogRequest, UiRotateLogResponse,
+    UiTargetLogLevel, LOG_LEVEL_ERROR, LOG_ROTATION_ERROR,
 };
-use masq_lib::ui_gateway::{MessageTarget, NodeFromUiMessage, NodeToUiMessage};
+use masq_lib::ui_gateway::MessagePath::Conversation;
+use masq_lib::ui_gateway::{MessageBody, MessageTarget, NodeFromUiMessage, NodeToUiMessage};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
 
 pub const CRASH_KEY: &str = "DISPATCHER";
 
@@ -32,6 +40,7 @@ pub struct Dispatcher {
     crashable: bool,
     node_descriptor: String,
     to_stream: Option<Recipient<TransmitDataMsg>>,
+    to_stream_punch_hole: Option<Recipient<PunchHoleMsg>>,
     logger: Logger,
 }
 
@@ -60,6 +69,7 @@ impl Handler<PoolBindMessage> for Dispatcher {
 
     fn handle(&mut self, msg: PoolBindMessage, _ctx: &mut Self::Context) {
         self.to_stream = Some(msg.stream_handler_pool_subs.transmit_sub);
+        self.to_stream_punch_hole = Some(msg.stream_handler_pool_subs.punch_hole_sub);
     }
 }
 
@@ -103,6 +113,18 @@ impl Handler<TransmitDataMsg> for Dispatcher {
     }
 }
 
+impl Handler<PunchHoleMsg> for Dispatcher {
+    type Result = ();
+
+    fn handle(&mut self, msg: PunchHoleMsg, _ctx: &mut Self::Context) {
+        self.to_stream_punch_hole
+            .as_ref()
+            .expect("StreamHandlerPool unbound in Dispatcher")
+            .try_send(msg)
+            .expect("StreamHandlerPool is dead");
+    }
+}
+
 impl Handler<StreamShutdownMsg> for Dispatcher {
     type Result = ();
 
@@ -117,6 +139,10 @@ impl Handler<NodeFromUiMessage> for Dispatcher {
     fn handle(&mut self, msg: NodeFromUiMessage, _ctx: &mut Self::Context) -> Self::Result {
         if let Ok((crash_request, _)) = UiCrashRequest::fmb(msg.body.clone()) {
             handle_ui_crash_request(crash_request, &self.logger, self.crashable, CRASH_KEY);
+        } else if let Ok((request, context_id)) = UiLogLevelRequest::fmb(msg.body.clone()) {
+            self.handle_log_level_request(msg.client_id, context_id, request);
+        } else if let Ok((_, context_id)) = UiRotateLogRequest::fmb(msg.body.clone()) {
+            self.handle_rotate_log_request(msg.client_id, context_id);
         } else if let Ok((_, context_id)) = UiDescriptorRequest::fmb(msg.body) {
             self.handle_descriptor_request(msg.client_id, context_id);
         }
@@ -130,6 +156,7 @@ impl Dispatcher {
             crashable: crash_point == CrashPoint::Message,
             node_descriptor,
             to_stream: None,
+            to_stream_punch_hole: None,
             logger: Logger::new("Dispatcher"),
         }
     }
@@ -141,6 +168,7 @@ impl Dispatcher {
             from_dispatcher_client: addr.clone().recipient::<TransmitDataMsg>(),
             stream_shutdown_sub: addr.clone().recipient::<StreamShutdownMsg>(),
             ui_sub: addr.clone().recipient::<NodeFromUiMessage>(),
+            punch_hole_sub: addr.clone().recipient::<PunchHoleMsg>(),
         }
     }
 
@@ -171,6 +199,118 @@ impl Dispatcher {
             .try_send(response_msg)
             .expect("UiGateway is dead");
     }
+
+    fn handle_log_level_request(
+        &mut self,
+        client_id: u64,
+        context_id: u64,
+        request: UiLogLevelRequest,
+    ) {
+        let body = match Self::parse_log_level_request(request) {
+            Ok((default_opt, targets_opt)) => {
+                let levels = change_log_levels(default_opt, targets_opt);
+                info!(
+                    self.logger,
+                    "UI {} changed the log levels to {} with {:?}",
+                    client_id,
+                    levels.default,
+                    levels.targets
+                );
+                Self::log_level_response(levels).tmb(context_id)
+            }
+            Err(e) => MessageBody {
+                opcode: UiLogLevelRequest::type_opcode().to_string(),
+                path: Conversation(context_id),
+                payload: Err((LOG_LEVEL_ERROR, e)),
+            },
+        };
+        let subs = self.subs.as_ref().expect("Dispatcher is unbound");
+        subs.ui_gateway_sub
+            .try_send(NodeToUiMessage {
+                target: MessageTarget::ClientId(client_id),
+                body,
+            })
+            .expect("UiGateway is dead");
+    }
+
+    fn handle_rotate_log_request(&mut self, client_id: u64, context_id: u64) {
+        let rotation_result = rotate_log_now();
+        match &rotation_result {
+            Ok(Some(archive)) => info!(
+                self.logger,
+                "UI {} rotated the log into {}",
+                client_id,
+                archive.display()
+            ),
+            Ok(None) => (),
+            Err(e) => warning!(
+                self.logger,
+                "UI {} couldn't rotate the log: {}",
+                client_id,
+                e
+            ),
+        }
+        let subs = self.subs.as_ref().expect("Dispatcher is unbound");
+        subs.ui_gateway_sub
+            .try_send(NodeToUiMessage {
+                target: MessageTarget::ClientId(client_id),
+                body: Self::rotate_log_response(rotation_result, context_id),
+            })
+            .expect("UiGateway is dead");
+    }
+
+    fn rotate_log_response(
+        rotation_result: Result<Option<PathBuf>, String>,
+        context_id: u64,
+    ) -> MessageBody {
+        match rotation_result {
+            Ok(archive_opt) => UiRotateLogResponse {
+                archive_opt: archive_opt.map(|archive| archive.to_string_lossy().to_string()),
+            }
+            .tmb(context_id),
+            Err(e) => MessageBody {
+                opcode: UiRotateLogRequest::type_opcode().to_string(),
+                path: Conversation(context_id),
+                payload: Err((LOG_ROTATION_ERROR, e)),
+            },
+        }
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn parse_log_level_request(
+        request: UiLogLevelRequest,
+    ) -> Result<(Option<LevelFilter>, Option<BTreeMap<String, LevelFilter>>), String> {
+        let default_opt = request
+            .level_opt
+            .map(|name| parse_level(&name))
+            .transpose()?;
+        let targets_opt = request
+            .target_levels_opt
+            .map(|target_levels| {
+                target_levels
+                    .into_iter()
+                    .map(|target_level| {
+                        parse_level(&target_level.level).map(|level| (target_level.target, level))
+                    })
+                    .collect::<Result<BTreeMap<String, LevelFilter>, String>>()
+            })
+            .transpose()?;
+        Ok((default_opt, targets_opt))
+    }
+
+    fn log_level_response(levels: LogLevels) -> UiLogLevelResponse {
+        UiLogLevelResponse {
+            level: levels.default.to_string(),
+            target_levels: levels
+                .targets
+                .into_iter()
+                .map(|(target, level)| UiTargetLogLevel {
+                    target,
+                    level: level.to_string(),
+                })
+                .collect(),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -332,6 +472,7 @@ mod tests {
             last_data: false,
             sequence_number: Some(0),
             data: data.clone(),
+            relayed_from_opt: None,
         };
 
         subject_obcd.try_send(obcd).unwrap();
@@ -356,6 +497,7 @@ mod tests {
             last_data: false,
             sequence_number: None,
             data: data.clone(),
+            relayed_from_opt: None,
         };
         let mut peer_actors = peer_actors_builder().build();
         peer_actors.dispatcher = Dispatcher::make_subs_from(&subject_addr);
@@ -387,6 +529,42 @@ mod tests {
         assert_eq!(recording.len(), 1);
     }
 
+    #[test]
+    fn forwards_punch_hole_msg_to_stream_handler_pool() {
+        let system = System::new("test");
+        let subject = Dispatcher::new(CrashPoint::None, "descriptor".to_string());
+        let subject_addr: Addr<Dispatcher> = subject.start();
+        let stream_handler_pool = Recorder::new();
+        let recording_arc = stream_handler_pool.get_recording();
+        let awaiter = stream_handler_pool.get_awaiter();
+        let msg = PunchHoleMsg {
+            peer_addr: SocketAddr::from_str("1.2.3.4:5678").unwrap(),
+            local_port: 4321,
+        };
+        let mut peer_actors = peer_actors_builder().build();
+        peer_actors.dispatcher = Dispatcher::make_subs_from(&subject_addr);
+        let stream_handler_pool_subs =
+            make_stream_handler_pool_subs_from(Some(stream_handler_pool));
+        subject_addr
+            .try_send(PoolBindMessage {
+                dispatcher_subs: peer_actors.dispatcher.clone(),
+                stream_handler_pool_subs,
+                neighborhood_subs: peer_actors.neighborhood.clone(),
+            })
+            .unwrap();
+        subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+
+        subject_addr.try_send(msg.clone()).unwrap();
+
+        System::current().stop_with_code(0);
+        system.run();
+
+        awaiter.await_message_count(1);
+        let recording = recording_arc.lock().unwrap();
+        assert_eq!(recording.get_record::<PunchHoleMsg>(0), &msg);
+        assert_eq!(recording.len(), 1);
+    }
+
     #[test]
     fn handle_stream_shutdown_msg_routes_non_clandestine_to_proxy_server() {
         let system = System::new("test");
@@ -404,6 +582,7 @@ mod tests {
             stream_type: RemovedStreamType::NonClandestine(NonClandestineAttributes {
                 reception_port: HTTP_PORT,
                 sequence_number: 1234,
+                half_closed: false,
             }),
             report_to_counterpart: true,
         };
@@ -483,4 +662,92 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn log_level_request_changes_the_log_levels_and_reports_them() {
+        let system = System::new("test");
+        let subject = Dispatcher::new(CrashPoint::None, "Node descriptor".to_string());
+        let addr = subject.start();
+        let (ui_gateway_recorder, _, ui_gateway_recording_arc) = make_recorder();
+        let peer_actors = peer_actors_builder()
+            .ui_gateway(ui_gateway_recorder)
+            .build();
+        addr.try_send(BindMessage { peer_actors }).unwrap();
+        let msg = NodeFromUiMessage {
+            client_id: 1234,
+            body: UiLogLevelRequest {
+                level_opt: Some("info".to_string()),
+                target_levels_opt: Some(vec![UiTargetLogLevel {
+                    target: "Neighborhood".to_string(),
+                    level: "trace".to_string(),
+                }]),
+            }
+            .tmb(4321),
+        };
+
+        addr.try_send(msg).unwrap();
+
+        System::current().stop_with_code(0);
+        system.run();
+        let ui_gateway_recording = ui_gateway_recording_arc.lock().unwrap();
+        assert_eq!(
+            ui_gateway_recording.get_record::<NodeToUiMessage>(0),
+            &NodeToUiMessage {
+                target: MessageTarget::ClientId(1234),
+                body: UiLogLevelResponse {
+                    level: "INFO".to_string(),
+                    target_levels: vec![UiTargetLogLevel {
+                        target: "Neighborhood".to_string(),
+                        level: "TRACE".to_string(),
+                    }],
+                }
+                .tmb(4321)
+            }
+        );
+    }
+
+    #[test]
+    fn log_level_request_with_a_bad_level_changes_nothing() {
+        let result = Dispatcher::parse_log_level_request(UiLogLevelRequest {
+            level_opt: Some("info".to_string()),
+            target_levels_opt: Some(vec![UiTargetLogLevel {
+                target: "Neighborhood".to_string(),
+                level: "loud".to_string(),
+            }]),
+        });
+
+        assert_eq!(result, Err("'loud' is not a log level".to_string()));
+    }
+
+    #[test]
+    fn rotate_log_response_names_the_archive_or_the_problem() {
+        let rotated = Dispatcher::rotate_log_response(
+            Ok(Some(PathBuf::from(
+                "/data/MASQNode_2020-10-01T12-34-56.789.log.gz",
+            ))),
+            1234,
+        );
+        let nothing_to_rotate = Dispatcher::rotate_log_response(Ok(None), 2345);
+        let failed = Dispatcher::rotate_log_response(Err("Disk full".to_string()), 3456);
+
+        assert_eq!(
+            rotated,
+            UiRotateLogResponse {
+                archive_opt: Some("/data/MASQNode_2020-10-01T12-34-56.789.log.gz".to_string())
+            }
+            .tmb(1234)
+        );
+        assert_eq!(
+            nothing_to_rotate,
+            UiRotateLogResponse { archive_opt: None }.tmb(2345)
+        );
+        assert_eq!(
+            failed,
+            MessageBody {
+                opcode: "rotateLog".to_string(),
+                path: Conversation(3456),
+                payload: Err((LOG_ROTATION_ERROR, "Disk full".to_string())),
+            }
+        );
+    }
 }
@@ -1,13 +1,17 @@
 // Copyright (c) 2017-2019, Substratum LLC (https://substratum.net) and/or its affiliates. All rights reserved.
+use crate::node_configurator::node_configurator_standard::NodeConfiguratorStandardPrivileged;
+use crate::node_configurator::NodeConfigurator;
 use crate::sub_lib::logger::Logger;
 use crate::sub_lib::socket_server::SocketServer;
 use masq_lib::command::StdStreams;
-use std::net::SocketAddr;
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
 use tokio::prelude::Async;
 use tokio::prelude::Future;
 
-const DNS_PORT: u16 = 53;
+pub const DNS_PORT: u16 = 53;
 
+use crate::entry_dns::packet_facade::PacketFacade;
 use crate::entry_dns::processing;
 use crate::sub_lib::udp_socket_wrapper::UdpSocketWrapperReal;
 use crate::sub_lib::udp_socket_wrapper::UdpSocketWrapperTrait;
@@ -16,6 +20,13 @@ use masq_lib::utils::localhost;
 
 pub struct DnsSocketServer {
     socket_wrapper: Box<dyn UdpSocketWrapperTrait>,
+    upstream_socket_wrapper: Box<dyn UdpSocketWrapperTrait>,
+    // None: every query is answered with our own address, and nothing goes upstream
+    proxied_domains_opt: Option<Vec<String>>,
+    upstream_addr: SocketAddr,
+    // transaction ID sent upstream -> (client's transaction ID, client's address)
+    forwarded_queries: HashMap<u16, (u16, SocketAddr)>,
+    next_transaction_id: u16,
     buf: [u8; 65536],
 }
 
@@ -29,7 +40,7 @@ impl Future for DnsSocketServer {
             let mut buffer = self.buf;
             let (len, socket_addr) = match self.socket_wrapper.recv_from(&mut buffer) {
                 Ok(Async::Ready((len, socket_addr))) => (len, socket_addr),
-                Ok(Async::NotReady) => return Ok(Async::NotReady),
+                Ok(Async::NotReady) => break,
                 Err(e) => {
                     error!(
                         logger,
@@ -38,6 +49,14 @@ impl Future for DnsSocketServer {
                     return Err(());
                 }
             };
+            let proxied = match &self.proxied_domains_opt {
+                Some(proxied_domains) => processing::is_proxied(&mut buffer, len, proxied_domains),
+                None => true,
+            };
+            if !proxied {
+                self.forward_query(&mut buffer[0..len], socket_addr, &logger);
+                continue;
+            }
             let response_length = processing::process(&mut buffer, len, &socket_addr, &logger);
             if let Err(e) = self
                 .socket_wrapper
@@ -47,6 +66,41 @@ impl Future for DnsSocketServer {
                 return Err(());
             }
         }
+        if self.proxied_domains_opt.is_none() {
+            return Ok(Async::NotReady);
+        }
+        loop {
+            let mut buffer = self.buf;
+            let len = match self.upstream_socket_wrapper.recv_from(&mut buffer) {
+                Ok(Async::Ready((len, _))) => len,
+                Ok(Async::NotReady) => return Ok(Async::NotReady),
+                Err(e) => {
+                    // Probably an ICMP error left over from an earlier send: try again later
+                    warning!(logger, "Error receiving from upstream DNS server: {}", e);
+                    futures::task::current().notify();
+                    return Ok(Async::NotReady);
+                }
+            };
+            let mut facade = PacketFacade::new(&mut buffer, len);
+            let (transaction_id, client_addr) = match facade
+                .get_transaction_id()
+                .and_then(|id| self.forwarded_queries.remove(&id))
+            {
+                Some(pair) => pair,
+                None => {
+                    debug!(
+                        logger,
+                        "Discarding unsolicited reply from upstream DNS server"
+                    );
+                    continue;
+                }
+            };
+            facade.set_transaction_id(transaction_id);
+            if let Err(e) = self.socket_wrapper.send_to(&buffer[0..len], client_addr) {
+                error!(logger, "Unrecoverable error sending to UdpSocket: {}", e);
+                return Err(());
+            }
+        }
     }
 }
 
@@ -57,13 +111,30 @@ impl SocketServer<()> for DnsSocketServer {
 
     fn initialize_as_privileged(
         &mut self,
-        _args: &[String],
-        _streams: &mut StdStreams<'_>,
+        args: &[String],
+        streams: &mut StdStreams<'_>,
     ) -> Result<(), ConfiguratorError> {
-        let socket_addr = SocketAddr::new(localhost(), DNS_PORT);
+        // The Bootstrapper reports any problems with the parameters, so here the defaults will do
+        let config = NodeConfiguratorStandardPrivileged::new()
+            .configure(args, streams)
+            .unwrap_or_default();
+        let socket_addr = SocketAddr::new(localhost(), config.dns_port);
         self.socket_wrapper
             .bind(socket_addr)
             .unwrap_or_else(|e| panic!("Cannot bind socket to {:?}: {:?}", socket_addr, e));
+        if config.proxied_domains_opt.is_some() {
+            if let Some(upstream_addr) = config.dns_servers.first() {
+                self.upstream_addr = *upstream_addr;
+                let local_addr = match upstream_addr.ip() {
+                    IpAddr::V4(_) => SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0),
+                    IpAddr::V6(_) => SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), 0),
+                };
+                self.upstream_socket_wrapper
+                    .bind(local_addr)
+                    .unwrap_or_else(|e| panic!("Cannot bind socket to {:?}: {:?}", local_addr, e));
+                self.proxied_domains_opt = config.proxied_domains_opt;
+            }
+        }
         Ok(())
     }
 
@@ -81,9 +152,44 @@ impl DnsSocketServer {
     pub fn new() -> DnsSocketServer {
         DnsSocketServer {
             socket_wrapper: Box::new(UdpSocketWrapperReal::new()),
+            upstream_socket_wrapper: Box::new(UdpSocketWrapperReal::new()),
+            proxied_domains_opt: None,
+            upstream_addr: SocketAddr::new(IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1)), DNS_PORT),
+            forwarded_queries: HashMap::new(),
+            next_transaction_id: 0,
             buf: [0; 65536],
         }
     }
+
+    // Queries we don't answer ourselves go to the upstream server under a transaction ID of our own,
+    // so that replies to different clients who happened to pick the same ID can't be confused.
+    fn forward_query(&mut self, query: &mut [u8], client_addr: SocketAddr, logger: &Logger) {
+        let len = query.len();
+        let mut facade = PacketFacade::new(query, len);
+        let transaction_id = match facade.get_transaction_id() {
+            Some(id) => id,
+            None => return,
+        };
+        let upstream_transaction_id = self.next_transaction_id;
+        self.next_transaction_id = self.next_transaction_id.wrapping_add(1);
+        facade.set_transaction_id(upstream_transaction_id);
+        match self
+            .upstream_socket_wrapper
+            .send_to(query, self.upstream_addr)
+        {
+            Ok(_) => {
+                self.forwarded_queries
+                    .insert(upstream_transaction_id, (transaction_id, client_addr));
+            }
+            Err(e) => warning!(
+                logger,
+                "Could not forward query from {} to {}: {}",
+                client_addr,
+                self.upstream_addr,
+                e
+            ),
+        }
+    }
 }
 
 impl Default for DnsSocketServer {
@@ -99,6 +205,7 @@ mod tests {
     use crate::sub_lib::udp_socket_wrapper::UdpSocketWrapperTrait;
     use crate::test_utils::logging::init_test_logging;
     use crate::test_utils::logging::TestLogHandler;
+    use crate::test_utils::ArgsBuilder;
     use masq_lib::test_utils::fake_stream_holder::FakeStreamHolder;
     use std::borrow::Borrow;
     use std::borrow::BorrowMut;
@@ -112,11 +219,12 @@ mod tests {
     use std::sync::Arc;
     use std::sync::Mutex;
     use tokio;
-    use trust_dns::op::ResponseCode;
+    use trust_dns::op::{OpCode, ResponseCode};
+    use trust_dns::rr::{DNSClass, RecordType};
 
     struct UdpSocketWrapperMockGuts {
         log: Vec<String>,
-        buf: [u8; 12],
+        buf: Vec<u8>,
     }
 
     #[derive(Clone)]
@@ -155,36 +263,21 @@ mod tests {
             let guts_ref = unwrapped_guts.borrow_mut();
             let guts: &mut UdpSocketWrapperMockGuts = guts_ref.deref_mut();
             guts.log.push(format!("send_to (buf, {:?})", addr));
-            UdpSocketWrapperMock::copy(&mut guts.buf, buf);
+            guts.buf = buf.to_vec();
             self.send_to_results.lock().unwrap().borrow_mut().remove(0)
         }
     }
 
     impl UdpSocketWrapperMock {
         fn new(buf: &[u8]) -> UdpSocketWrapperMock {
-            assert_eq!(
-                buf.len() <= 12,
-                true,
-                "Mock accepts buffer of up to 12 bytes, not {}",
-                buf.len()
-            );
-            let result = UdpSocketWrapperMock {
+            UdpSocketWrapperMock {
                 guts: Arc::new(Mutex::new(UdpSocketWrapperMockGuts {
                     log: Vec::new(),
-                    buf: [0; 12],
+                    buf: buf.to_vec(),
                 })),
                 recv_from_results: Arc::new(Mutex::new(vec![])),
                 send_to_results: Arc::new(Mutex::new(vec![])),
-            };
-            result
-                .guts
-                .lock()
-                .unwrap()
-                .borrow_mut()
-                .deref_mut()
-                .buf
-                .copy_from_slice(&buf);
-            result
+            }
         }
 
         fn copy(destination: &mut [u8], source: &[u8]) {
@@ -199,15 +292,173 @@ mod tests {
     fn uses_standard_dns_port() {
         let socket_wrapper = make_socket_wrapper_mock();
         let mut subject = make_instrumented_subject(socket_wrapper.clone());
+        let upstream_socket_wrapper = make_socket_wrapper_mock();
+        subject.upstream_socket_wrapper = upstream_socket_wrapper.clone();
+        let args: Vec<String> = ArgsBuilder::new().into();
 
         subject
-            .initialize_as_privileged(&[], &mut FakeStreamHolder::new().streams())
+            .initialize_as_privileged(&args, &mut FakeStreamHolder::new().streams())
             .unwrap();
 
         let unwrapped_guts = socket_wrapper.guts.lock().unwrap();
         let borrowed_guts = unwrapped_guts.borrow();
         let log = &borrowed_guts.log;
-        assert_eq!(log[0], "bind ('127.0.0.1:53')")
+        assert_eq!(log[0], "bind ('127.0.0.1:53')");
+        assert_eq!(
+            upstream_socket_wrapper.guts.lock().unwrap().log.is_empty(),
+            true
+        );
+    }
+
+    #[test]
+    fn uses_configured_dns_port_and_binds_upstream_socket_when_forwarding() {
+        let socket_wrapper = make_socket_wrapper_mock();
+        let upstream_socket_wrapper = make_socket_wrapper_mock();
+        let mut subject = make_instrumented_subject(socket_wrapper.clone());
+        subject.upstream_socket_wrapper = upstream_socket_wrapper.clone();
+        let args: Vec<String> = ArgsBuilder::new()
+            .param("--dns-port", "5353")
+            .param("--dns-servers", "8.8.8.8")
+            .param("--proxied-domains", "Example.com")
+            .into();
+
+        subject
+            .initialize_as_privileged(&args, &mut FakeStreamHolder::new().streams())
+            .unwrap();
+
+        assert_eq!(
+            socket_wrapper.guts.lock().unwrap().log,
+            vec!["bind ('127.0.0.1:5353')".to_string()]
+        );
+        assert_eq!(
+            upstream_socket_wrapper.guts.lock().unwrap().log,
+            vec!["bind ('0.0.0.0:0')".to_string()]
+        );
+        assert_eq!(
+            subject.upstream_addr,
+            SocketAddr::from_str("8.8.8.8:53").unwrap()
+        );
+        assert_eq!(
+            subject.proxied_domains_opt,
+            Some(vec!["example.com".to_string()])
+        );
+    }
+
+    #[test]
+    fn answers_queries_for_proxied_domains_itself() {
+        let query = make_query("www.example.com");
+        let socket_wrapper = Box::new(UdpSocketWrapperMock::new(&query));
+        let client_addr = SocketAddr::from_str("127.0.0.1:4567").unwrap();
+        socket_wrapper
+            .recv_from_results
+            .lock()
+            .unwrap()
+            .push(Ok(Async::Ready((query.len(), client_addr))));
+        socket_wrapper
+            .recv_from_results
+            .lock()
+            .unwrap()
+            .push(Ok(Async::NotReady));
+        socket_wrapper
+            .send_to_results
+            .lock()
+            .unwrap()
+            .push(Ok(Async::Ready(0)));
+        let upstream_socket_wrapper = make_socket_wrapper_mock();
+        upstream_socket_wrapper
+            .recv_from_results
+            .lock()
+            .unwrap()
+            .push(Ok(Async::NotReady));
+        let mut subject = make_forwarding_subject(
+            socket_wrapper.clone(),
+            upstream_socket_wrapper.clone(),
+            "example.com",
+        );
+
+        let result = subject.poll();
+
+        assert_eq!(result, Ok(Async::NotReady));
+        let mut guts = socket_wrapper.guts.lock().unwrap();
+        assert_eq!(guts.log[1], "send_to (buf, 127.0.0.1:4567)");
+        let length = guts.buf.len();
+        let facade = PacketFacade::new(&mut guts.buf, length);
+        assert_eq!(facade.get_transaction_id(), Some(0x1234));
+        assert_eq!(
+            facade.get_answers().unwrap()[0].get_rdata(),
+            &[127, 0, 0, 1]
+        );
+        assert_eq!(
+            upstream_socket_wrapper.guts.lock().unwrap().log,
+            vec!["recv_from (Ok(NotReady))".to_string()]
+        );
+    }
+
+    #[test]
+    fn forwards_queries_for_other_domains_and_relays_replies() {
+        let query = make_query("www.example.org");
+        let socket_wrapper = Box::new(UdpSocketWrapperMock::new(&query));
+        let client_addr = SocketAddr::from_str("127.0.0.1:4567").unwrap();
+        let upstream_addr = SocketAddr::from_str("8.8.8.8:53").unwrap();
+        socket_wrapper
+            .recv_from_results
+            .lock()
+            .unwrap()
+            .push(Ok(Async::Ready((query.len(), client_addr))));
+        socket_wrapper
+            .recv_from_results
+            .lock()
+            .unwrap()
+            .push(Ok(Async::NotReady));
+        socket_wrapper
+            .send_to_results
+            .lock()
+            .unwrap()
+            .push(Ok(Async::Ready(query.len())));
+        // The upstream mock hands back whatever was sent to it, so the "reply" is the forwarded query
+        let upstream_socket_wrapper = Box::new(UdpSocketWrapperMock::new(&[]));
+        upstream_socket_wrapper
+            .send_to_results
+            .lock()
+            .unwrap()
+            .push(Ok(Async::Ready(query.len())));
+        upstream_socket_wrapper
+            .recv_from_results
+            .lock()
+            .unwrap()
+            .push(Ok(Async::Ready((query.len(), upstream_addr))));
+        upstream_socket_wrapper
+            .recv_from_results
+            .lock()
+            .unwrap()
+            .push(Ok(Async::NotReady));
+        let mut subject = make_forwarding_subject(
+            socket_wrapper.clone(),
+            upstream_socket_wrapper.clone(),
+            "example.com",
+        );
+        subject.upstream_addr = upstream_addr;
+        subject.next_transaction_id = 0x4321;
+
+        let result = subject.poll();
+
+        assert_eq!(result, Ok(Async::NotReady));
+        let mut upstream_guts = upstream_socket_wrapper.guts.lock().unwrap();
+        assert_eq!(upstream_guts.log[0], "send_to (buf, 8.8.8.8:53)");
+        let length = upstream_guts.buf.len();
+        let forwarded = PacketFacade::new(&mut upstream_guts.buf, length);
+        assert_eq!(forwarded.get_transaction_id(), Some(0x4321));
+        let mut guts = socket_wrapper.guts.lock().unwrap();
+        assert_eq!(guts.log[2], "send_to (buf, 127.0.0.1:4567)");
+        let length = guts.buf.len();
+        let relayed = PacketFacade::new(&mut guts.buf, length);
+        assert_eq!(relayed.get_transaction_id(), Some(0x1234));
+        assert_eq!(
+            relayed.get_queries().unwrap()[0].get_query_name(),
+            "www.example.org"
+        );
+        assert_eq!(relayed.get_answers().unwrap().is_empty(), true);
+        assert_eq!(subject.forwarded_queries.is_empty(), true);
     }
 
     #[test]
@@ -365,10 +616,37 @@ mod tests {
         ]))
     }
 
+    fn make_query(name: &str) -> Vec<u8> {
+        let mut buf = vec![0u8; 100];
+        let length = {
+            let mut facade = PacketFacade::new(&mut buf, 100);
+            facade.set_transaction_id(0x1234);
+            facade.set_query(true);
+            facade.set_opcode(OpCode::Query.into());
+            facade.add_query(name, RecordType::A.into(), DNSClass::IN.into());
+            facade.get_length()
+        };
+        buf.truncate(length);
+        buf
+    }
+
     fn make_instrumented_subject(socket_wrapper: Box<UdpSocketWrapperMock>) -> DnsSocketServer {
         DnsSocketServer {
             socket_wrapper,
             buf: [0; 65536],
+            ..DnsSocketServer::new()
+        }
+    }
+
+    fn make_forwarding_subject(
+        socket_wrapper: Box<UdpSocketWrapperMock>,
+        upstream_socket_wrapper: Box<UdpSocketWrapperMock>,
+        proxied_domain: &str,
+    ) -> DnsSocketServer {
+        DnsSocketServer {
+            upstream_socket_wrapper,
+            proxied_domains_opt: Some(vec![proxied_domain.to_string()]),
+            ..make_instrumented_subject(socket_wrapper)
         }
     }
 }
@@ -36,6 +36,23 @@ pub fn process(buf: &mut [u8], length: usize, addr: &SocketAddr, logger: &Logger
     response_size
 }
 
+// A query is ours to answer with our own address if every name in it is one of the proxied domains
+// or a subdomain of one. Anything too mangled to read is answered here too, with an error.
+pub fn is_proxied(buf: &mut [u8], length: usize, proxied_domains: &[String]) -> bool {
+    let facade = PacketFacade::new(buf, length);
+    match facade.get_queries() {
+        None => true,
+        Some(queries) => queries.iter().all(|query| {
+            let name = query.get_query_name().trim_end_matches('.').to_lowercase();
+            proxied_domains.iter().any(|domain| {
+                name == *domain
+                    || (name.ends_with(domain.as_str())
+                        && name[..name.len() - domain.len()].ends_with('.'))
+            })
+        }),
+    }
+}
+
 fn make_response(mut facade: &mut PacketFacade) -> usize {
     match facade.get_opcode() {
         None => return make_format_error(facade),
@@ -188,6 +205,33 @@ mod tests {
     use std::net::SocketAddrV4;
     use std::time::Instant;
 
+    #[test]
+    fn queries_for_proxied_domains_and_their_subdomains_are_proxied() {
+        let proxied_domains = vec!["example.com".to_string()];
+        let make_query = |name: &str| {
+            let mut buf: Vec<u8> = vec![0; 500];
+            let length = {
+                let mut facade = PacketFacade::new(&mut buf, 500);
+                facade.set_transaction_id(0x1234);
+                facade.set_query(true);
+                facade.set_opcode(OpCode::Query.into());
+                facade.add_query(name, RecordType::A.into(), DNSClass::IN.into());
+                facade.get_length()
+            };
+            (buf, length)
+        };
+        let check = |name: &str| {
+            let (mut buf, length) = make_query(name);
+            is_proxied(&mut buf, length, &proxied_domains)
+        };
+
+        assert_eq!(check("example.com"), true);
+        assert_eq!(check("WWW.Example.com."), true);
+        assert_eq!(check("notexample.com"), false);
+        assert_eq!(check("example.com.au"), false);
+        assert_eq!(check("example.org"), false);
+    }
+
     #[test]
     fn returns_format_error_if_queries_overrun() {
         let memory: [u8; 100] = [0; 100];
@@ -0,0 +1,560 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use crate::proxy_client::secure_resolver::https_response_body;
+use crate::sub_lib::logger::Logger;
+use crate::updater::https_get;
+use crossbeam_channel::{bounded, Receiver, RecvTimeoutError, Sender};
+use maxminddb::geoip2;
+use maxminddb::Reader;
+use rustc_hex::FromHex;
+use sodiumoxide::crypto::sign as signing;
+use std::fs;
+use std::io;
+use std::io::Read;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+pub const DEFAULT_GEO_DB_UPDATE_INTERVAL_HOURS: u64 = 168;
+pub const GEO_DB_FILENAME: &str = "geo-ip.mmdb";
+// Comfortably bigger than the GeoIP City and ASN databases together
+const MAX_GEO_DB_LEN: usize = 256 * 1024 * 1024;
+const MAX_SIGNATURE_LEN: usize = 1024;
+// maxminddb panics rather than failing on a file that doesn't have this in it
+const METADATA_MARKER: &[u8] = b"\xAB\xCD\xEFMaxMind.com";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GeoDbConfig {
+    pub mirror_url: String,
+    pub public_key: Vec<u8>,
+    pub interval: Duration,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GeoDbStatus {
+    // When the database on disk was downloaded and verified
+    pub updated_at_opt: Option<SystemTime>,
+    pub checked_at_opt: Option<SystemTime>,
+    pub error_opt: Option<String>,
+    pub refreshing: bool,
+}
+
+pub trait GeoDbFetcher: Send {
+    fn fetch(&self, url: &str, max_len: usize) -> io::Result<Vec<u8>>;
+}
+
+pub struct GeoDbFetcherReal {}
+
+impl GeoDbFetcher for GeoDbFetcherReal {
+    fn fetch(&self, url: &str, max_len: usize) -> io::Result<Vec<u8>> {
+        let mut tls_stream = https_get(url, "application/octet-stream")?;
+        let mut response = vec![];
+        let mut buf = [0u8; 65536];
+        // The database is too big to look for the end of the body after every read, so it comes
+        // down to the end of the stream and the signature tells us whether we got all of it
+        loop {
+            match tls_stream.read(&mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(len) => response.extend_from_slice(&buf[..len]),
+            }
+            if response.len() > max_len + 65536 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{} is more than {} bytes long", url, max_len),
+                ));
+            }
+        }
+        match https_response_body(&response, true) {
+            Ok(Some(body)) => Ok(body),
+            Ok(None) => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("{} ended early", url),
+            )),
+            Err(msg) => Err(io::Error::new(io::ErrorKind::InvalidData, msg)),
+        }
+    }
+}
+
+pub struct GeoDbUpdater {
+    config: GeoDbConfig,
+    database_path: PathBuf,
+    fetcher: Box<dyn GeoDbFetcher>,
+    status: Arc<Mutex<GeoDbStatus>>,
+    logger: Logger,
+}
+
+impl GeoDbUpdater {
+    pub fn new(config: GeoDbConfig, data_directory: &Path) -> Self {
+        let database_path = data_directory.join(GEO_DB_FILENAME);
+        let status = GeoDbStatus {
+            updated_at_opt: fs::metadata(&database_path)
+                .and_then(|metadata| metadata.modified())
+                .ok(),
+            ..GeoDbStatus::default()
+        };
+        Self {
+            config,
+            database_path,
+            fetcher: Box::new(GeoDbFetcherReal {}),
+            status: Arc::new(Mutex::new(status)),
+            logger: Logger::new("GeoDbUpdater"),
+        }
+    }
+
+    // The mirror serves the database at its URL and the hex Ed25519 signature of it at the same
+    // URL with .sig on the end. Returns the size of the new database.
+    pub fn update(&self) -> Result<usize, String> {
+        let signature_url = format!("{}.sig", self.config.mirror_url);
+        let signature_hex = self
+            .fetcher
+            .fetch(&signature_url, MAX_SIGNATURE_LEN)
+            .map_err(|e| format!("Couldn't fetch {}: {}", signature_url, e))?;
+        let database = self
+            .fetcher
+            .fetch(&self.config.mirror_url, MAX_GEO_DB_LEN)
+            .map_err(|e| format!("Couldn't fetch {}: {}", self.config.mirror_url, e))?;
+        verify(&database, &signature_hex, &self.config.public_key)?;
+        // Written alongside and renamed into place, so that nobody ever reads half a database
+        let temp_path = self.database_path.with_extension("mmdb.new");
+        fs::write(&temp_path, &database)
+            .and_then(|_| fs::rename(&temp_path, &self.database_path))
+            .map_err(|e| {
+                format!(
+                    "Couldn't save the geolocation database to {:?}: {}",
+                    self.database_path, e
+                )
+            })?;
+        Ok(database.len())
+    }
+
+    pub fn update_and_report(&self) {
+        self.status
+            .lock()
+            .expect("GeoDbStatus is poisoned")
+            .refreshing = true;
+        let result = self.update();
+        let now = SystemTime::now();
+        let mut status = self.status.lock().expect("GeoDbStatus is poisoned");
+        status.refreshing = false;
+        status.checked_at_opt = Some(now);
+        match result {
+            Ok(len) => {
+                info!(
+                    self.logger,
+                    "Updated the geolocation database from {}: {} bytes",
+                    self.config.mirror_url,
+                    len
+                );
+                status.updated_at_opt = Some(now);
+                status.error_opt = None;
+            }
+            Err(e) => {
+                warning!(
+                    self.logger,
+                    "Couldn't update the geolocation database: {}",
+                    e
+                );
+                status.error_opt = Some(e);
+            }
+        }
+    }
+
+    // A database that's still fresh when the Node starts isn't downloaded again until it's due
+    fn next_wait(&self) -> Duration {
+        let status = self.status.lock().expect("GeoDbStatus is poisoned");
+        let last_attempt_opt = status.checked_at_opt.or(status.updated_at_opt);
+        match last_attempt_opt.and_then(|at| at.elapsed().ok()) {
+            Some(age) => self.config.interval.checked_sub(age).unwrap_or_default(),
+            None => Duration::from_secs(0),
+        }
+    }
+
+    fn run(self, refresh_rx: Receiver<()>) {
+        loop {
+            match refresh_rx.recv_timeout(self.next_wait()) {
+                Ok(()) | Err(RecvTimeoutError::Timeout) => self.update_and_report(),
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+    }
+}
+
+// What the rest of the Node holds to see how the geolocation database is doing and to ask for it
+// to be brought up to date
+#[derive(Clone)]
+pub struct GeoDbHandle {
+    pub mirror_url: String,
+    database_path: PathBuf,
+    status: Arc<Mutex<GeoDbStatus>>,
+    refresh_tx: Sender<()>,
+    reader: Arc<Mutex<Option<LoadedGeoDb>>>,
+}
+
+struct LoadedGeoDb {
+    updated_at_opt: Option<SystemTime>,
+    reader_opt: Option<Reader<Vec<u8>>>,
+}
+
+impl GeoDbHandle {
+    pub fn status(&self) -> GeoDbStatus {
+        self.status.lock().expect("GeoDbStatus is poisoned").clone()
+    }
+
+    // Asking again while a refresh is already waiting to start doesn't queue up another one
+    pub fn refresh(&self) {
+        let _ = self.refresh_tx.try_send(());
+    }
+
+    // The database is read from disk again whenever the updater has replaced it. None if there's
+    // no usable database or it doesn't know where the address is.
+    pub fn country_code(&self, ip: IpAddr) -> Option<String> {
+        let updated_at_opt = self.status().updated_at_opt;
+        let mut loaded_opt = self.reader.lock().expect("GeoDb reader is poisoned");
+        let stale = match loaded_opt.as_ref() {
+            Some(loaded) => loaded.updated_at_opt != updated_at_opt,
+            None => true,
+        };
+        if stale {
+            *loaded_opt = Some(LoadedGeoDb {
+                updated_at_opt,
+                reader_opt: read_geo_db(&self.database_path),
+            });
+        }
+        let reader = loaded_opt.as_ref()?.reader_opt.as_ref()?;
+        let record: geoip2::Country = reader.lookup(ip).ok()?;
+        record
+            .country
+            .and_then(|country| country.iso_code)
+            .map(|iso_code| iso_code.to_string())
+    }
+
+    #[cfg(test)]
+    pub fn new_for_test(mirror_url: &str, status: GeoDbStatus) -> (GeoDbHandle, Receiver<()>) {
+        let (refresh_tx, refresh_rx) = bounded(1);
+        let handle = GeoDbHandle {
+            mirror_url: mirror_url.to_string(),
+            database_path: PathBuf::from(GEO_DB_FILENAME),
+            status: Arc::new(Mutex::new(status)),
+            refresh_tx,
+            reader: Arc::new(Mutex::new(None)),
+        };
+        (handle, refresh_rx)
+    }
+
+    #[cfg(test)]
+    pub fn with_database_path(mut self, database_path: PathBuf) -> Self {
+        self.database_path = database_path;
+        self
+    }
+}
+
+pub fn start_geo_db_updater(config: GeoDbConfig, data_directory: &Path) -> GeoDbHandle {
+    start(GeoDbUpdater::new(config, data_directory))
+}
+
+fn start(updater: GeoDbUpdater) -> GeoDbHandle {
+    let (refresh_tx, refresh_rx) = bounded(1);
+    let handle = GeoDbHandle {
+        mirror_url: updater.config.mirror_url.clone(),
+        database_path: updater.database_path.clone(),
+        status: updater.status.clone(),
+        refresh_tx,
+        reader: Arc::new(Mutex::new(None)),
+    };
+    info!(
+        updater.logger,
+        "Keeping {:?} up to date from {} every {} hours",
+        updater.database_path,
+        updater.config.mirror_url,
+        updater.config.interval.as_secs() / 3600
+    );
+    thread::spawn(move || updater.run(refresh_rx));
+    handle
+}
+
+#[cfg(test)]
+// One search tree node: addresses whose first bit is 0 are in the US, the rest aren't anywhere
+pub fn tiny_country_database() -> Vec<u8> {
+    let mut database = vec![0, 0, 17, 0, 0, 1];
+    database.extend_from_slice(&[0u8; 16]);
+    database.extend_from_slice(b"\xE1\x47country\xE1\x48iso_code\x42US");
+    database.extend_from_slice(METADATA_MARKER);
+    database.extend_from_slice(b"\xE9");
+    database.extend_from_slice(b"\x4Anode_count\xC1\x01");
+    database.extend_from_slice(b"\x4Brecord_size\xA1\x18");
+    database.extend_from_slice(b"\x4Aip_version\xA1\x04");
+    database.extend_from_slice(b"\x4Ddatabase_type\x47Country");
+    database.extend_from_slice(b"\x49languages\x01\x04\x42en");
+    database.extend_from_slice(b"\x5Bbinary_format_major_version\xA1\x02");
+    database.extend_from_slice(b"\x5Bbinary_format_minor_version\xA0");
+    database.extend_from_slice(b"\x4Bbuild_epoch\x01\x02\x01");
+    database.extend_from_slice(b"\x4Bdescription\xE0");
+    database
+}
+
+fn read_geo_db(database_path: &Path) -> Option<Reader<Vec<u8>>> {
+    let database = fs::read(database_path).ok()?;
+    if !database
+        .windows(METADATA_MARKER.len())
+        .any(|window| window == METADATA_MARKER)
+    {
+        return None;
+    }
+    Reader::from_source(database).ok()
+}
+
+fn verify(database: &[u8], signature_hex: &[u8], public_key: &[u8]) -> Result<(), String> {
+    let signature_bytes: Vec<u8> = String::from_utf8_lossy(signature_hex)
+        .trim()
+        .from_hex()
+        .map_err(|_| "Geolocation database signature isn't hexadecimal".to_string())?;
+    let signature = signing::Signature::from_slice(&signature_bytes)
+        .ok_or_else(|| "Geolocation database signature is the wrong length".to_string())?;
+    let public_key = signing::PublicKey::from_slice(public_key)
+        .ok_or_else(|| "Geolocation database key is the wrong length".to_string())?;
+    if signing::verify_detached(&signature, database, &public_key) {
+        Ok(())
+    } else {
+        Err("Geolocation database signature doesn't match the geolocation database key".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::logging::{init_test_logging, TestLogHandler};
+    use crate::test_utils::wait_for;
+    use masq_lib::test_utils::utils::ensure_node_home_directory_exists;
+    use rustc_hex::ToHex;
+    use std::collections::HashMap;
+    use std::str::FromStr;
+
+    const MIRROR_URL: &str = "https://geo.example.com/geo-ip.mmdb";
+
+    struct GeoDbFetcherMock {
+        fetch_params: Arc<Mutex<Vec<(String, usize)>>>,
+        fetch_results: HashMap<String, Result<Vec<u8>, String>>,
+    }
+
+    impl GeoDbFetcher for GeoDbFetcherMock {
+        fn fetch(&self, url: &str, max_len: usize) -> io::Result<Vec<u8>> {
+            self.fetch_params
+                .lock()
+                .unwrap()
+                .push((url.to_string(), max_len));
+            self.fetch_results
+                .get(url)
+                .cloned()
+                .unwrap_or_else(|| Err(format!("Unexpected URL {}", url)))
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+        }
+    }
+
+    fn signature_of(database: &[u8], secret_key: &signing::SecretKey) -> Vec<u8> {
+        let signature = signing::sign_detached(database, secret_key);
+        let signature_hex: String = signature.0.to_hex();
+        format!("{}\n", signature_hex).into_bytes()
+    }
+
+    fn make_subject(
+        test_name: &str,
+        public_key: &signing::PublicKey,
+        database_result: Result<Vec<u8>, String>,
+        signature_result: Result<Vec<u8>, String>,
+    ) -> (GeoDbUpdater, Arc<Mutex<Vec<(String, usize)>>>) {
+        let data_directory = ensure_node_home_directory_exists("geo_db", test_name);
+        let fetch_params_arc = Arc::new(Mutex::new(vec![]));
+        let mut subject = GeoDbUpdater::new(
+            GeoDbConfig {
+                mirror_url: MIRROR_URL.to_string(),
+                public_key: public_key.0.to_vec(),
+                interval: Duration::from_secs(3600),
+            },
+            &data_directory,
+        );
+        subject.fetcher = Box::new(GeoDbFetcherMock {
+            fetch_params: fetch_params_arc.clone(),
+            fetch_results: vec![
+                (MIRROR_URL.to_string(), database_result),
+                (format!("{}.sig", MIRROR_URL), signature_result),
+            ]
+            .into_iter()
+            .collect(),
+        });
+        (subject, fetch_params_arc)
+    }
+
+    #[test]
+    fn country_code_comes_from_the_latest_database_on_disk() {
+        let data_directory = ensure_node_home_directory_exists(
+            "geo_db",
+            "country_code_comes_from_the_latest_database_on_disk",
+        );
+        let database_path = data_directory.join(GEO_DB_FILENAME);
+        fs::write(&database_path, tiny_country_database()).unwrap();
+        let first_update = SystemTime::now() - Duration::from_secs(60);
+        let (subject, _) = GeoDbHandle::new_for_test(
+            MIRROR_URL,
+            GeoDbStatus {
+                updated_at_opt: Some(first_update),
+                ..GeoDbStatus::default()
+            },
+        );
+        let subject = subject.with_database_path(database_path.clone());
+
+        let known = subject.country_code(IpAddr::from_str("1.2.3.4").unwrap());
+        let unknown = subject.country_code(IpAddr::from_str("200.1.2.3").unwrap());
+        fs::write(&database_path, b"not a geolocation database").unwrap();
+        let before_reload = subject.country_code(IpAddr::from_str("1.2.3.4").unwrap());
+        subject.status.lock().unwrap().updated_at_opt = Some(SystemTime::now());
+        let after_reload = subject.country_code(IpAddr::from_str("1.2.3.4").unwrap());
+
+        assert_eq!(known, Some("US".to_string()));
+        assert_eq!(unknown, None);
+        assert_eq!(before_reload, Some("US".to_string()));
+        assert_eq!(after_reload, None);
+    }
+
+    #[test]
+    fn country_code_is_none_without_a_database() {
+        let (subject, _) = GeoDbHandle::new_for_test(MIRROR_URL, GeoDbStatus::default());
+
+        let result = subject.country_code(IpAddr::from_str("1.2.3.4").unwrap());
+
+        assert_eq!(result, None);
+    }
+
+    #[test]
+    fn verified_database_is_saved_and_reported() {
+        init_test_logging();
+        let (public_key, secret_key) = signing::gen_keypair();
+        let database = b"pretend this is a GeoIP database".to_vec();
+        let (subject, fetch_params_arc) = make_subject(
+            "verified_database_is_saved_and_reported",
+            &public_key,
+            Ok(database.clone()),
+            Ok(signature_of(&database, &secret_key)),
+        );
+        let before = SystemTime::now();
+
+        subject.update_and_report();
+
+        assert_eq!(
+            *fetch_params_arc.lock().unwrap(),
+            vec![
+                (format!("{}.sig", MIRROR_URL), MAX_SIGNATURE_LEN),
+                (MIRROR_URL.to_string(), MAX_GEO_DB_LEN),
+            ]
+        );
+        assert_eq!(fs::read(&subject.database_path).unwrap(), database);
+        let status = subject.status.lock().unwrap().clone();
+        assert!(status.updated_at_opt.unwrap() >= before);
+        assert_eq!(status.checked_at_opt, status.updated_at_opt);
+        assert_eq!(status.error_opt, None);
+        assert_eq!(status.refreshing, false);
+        TestLogHandler::new().exists_log_containing(&format!(
+            "INFO: GeoDbUpdater: Updated the geolocation database from {}: 32 bytes",
+            MIRROR_URL
+        ));
+    }
+
+    #[test]
+    fn database_signed_with_another_key_is_not_saved() {
+        let (public_key, _) = signing::gen_keypair();
+        let (_, impostor_secret_key) = signing::gen_keypair();
+        let database = b"a database that puts every IP address in one country".to_vec();
+        let (subject, _) = make_subject(
+            "database_signed_with_another_key_is_not_saved",
+            &public_key,
+            Ok(database.clone()),
+            Ok(signature_of(&database, &impostor_secret_key)),
+        );
+        fs::write(&subject.database_path, b"the old database").unwrap();
+
+        subject.update_and_report();
+
+        assert_eq!(
+            fs::read(&subject.database_path).unwrap(),
+            b"the old database".to_vec()
+        );
+        let status = subject.status.lock().unwrap().clone();
+        assert!(status.checked_at_opt.is_some());
+        assert_eq!(
+            status.error_opt,
+            Some(
+                "Geolocation database signature doesn't match the geolocation database key"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn fetch_failure_is_reported() {
+        let (public_key, _) = signing::gen_keypair();
+        let (subject, _) = make_subject(
+            "fetch_failure_is_reported",
+            &public_key,
+            Ok(vec![]),
+            Err("Connection refused".to_string()),
+        );
+
+        let result = subject.update();
+
+        assert_eq!(
+            result,
+            Err(format!(
+                "Couldn't fetch {}.sig: Connection refused",
+                MIRROR_URL
+            ))
+        );
+    }
+
+    #[test]
+    fn database_already_on_disk_is_not_downloaded_again_until_it_is_due() {
+        let data_directory = ensure_node_home_directory_exists(
+            "geo_db",
+            "database_already_on_disk_is_not_downloaded_again_until_it_is_due",
+        );
+        let config = GeoDbConfig {
+            mirror_url: MIRROR_URL.to_string(),
+            public_key: vec![],
+            interval: Duration::from_secs(3600),
+        };
+        let missing = GeoDbUpdater::new(config.clone(), &data_directory);
+        fs::write(data_directory.join(GEO_DB_FILENAME), b"database").unwrap();
+
+        let present = GeoDbUpdater::new(config, &data_directory);
+
+        assert_eq!(missing.next_wait(), Duration::from_secs(0));
+        assert!(present.status.lock().unwrap().updated_at_opt.is_some());
+        let wait = present.next_wait();
+        assert!(
+            wait > Duration::from_secs(3590) && wait <= Duration::from_secs(3600),
+            "{:?}",
+            wait
+        );
+    }
+
+    #[test]
+    fn refresh_brings_a_fresh_database_up_to_date_right_away() {
+        let (public_key, secret_key) = signing::gen_keypair();
+        let database = b"the newest database".to_vec();
+        let (subject, fetch_params_arc) = make_subject(
+            "refresh_brings_a_fresh_database_up_to_date_right_away",
+            &public_key,
+            Ok(database.clone()),
+            Ok(signature_of(&database, &secret_key)),
+        );
+        subject.status.lock().unwrap().updated_at_opt = Some(SystemTime::now());
+        let database_path = subject.database_path.clone();
+        let handle = start(subject);
+
+        handle.refresh();
+
+        wait_for(None, None, || fetch_params_arc.lock().unwrap().len() == 2);
+        wait_for(None, None, || !handle.status().refreshing);
+        assert_eq!(fs::read(&database_path).unwrap(), database);
+        assert_eq!(handle.status().error_opt, None);
+    }
+}
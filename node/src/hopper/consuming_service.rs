@@ -1,8 +1,8 @@
 // Copyright (c) 2017-2019, Substratum LLC (https://substratum.net) and/or its affiliates. All rights reserved.
 
 use super::live_cores_package::LiveCoresPackage;
+use crate::sub_lib::cryptde::CryptDE;
 use crate::sub_lib::cryptde::CryptData;
-use crate::sub_lib::cryptde::{encodex, CryptDE};
 use crate::sub_lib::dispatcher::{Endpoint, InboundClientData};
 use crate::sub_lib::hopper::{IncipientCoresPackage, NoLookupIncipientCoresPackage};
 use crate::sub_lib::logger::Logger;
@@ -42,7 +42,7 @@ impl ConsumingService {
         let target_node_addr = incipient_cores_package.node_addr.clone();
         match LiveCoresPackage::from_no_lookup_incipient(incipient_cores_package, self.cryptde) {
             Ok((live_package, _)) => {
-                let encrypted_package = match encodex(self.cryptde, &target_key, &live_package) {
+                let encrypted_package = match live_package.encode(self.cryptde, &target_key) {
                     Ok(p) => p,
                     Err(e) => {
                         error!(
@@ -72,9 +72,10 @@ impl ConsumingService {
             incipient_cores_package.payload.len()
         );
         match LiveCoresPackage::from_incipient(incipient_cores_package, self.cryptde.borrow()) {
-            Ok((live_package, next_hop)) => {
+            Ok((mut live_package, next_hop)) => {
+                live_package.stamp_hop_timing(self.cryptde, &next_hop);
                 let encrypted_package =
-                    match encodex(self.cryptde, &next_hop.public_key, &live_package) {
+                    match live_package.encode(self.cryptde, &next_hop.public_key) {
                         Ok(p) => p,
                         Err(e) => {
                             error!(self.logger, "Couldn't encode package: {:?}", e);
@@ -114,6 +115,7 @@ impl ConsumingService {
             last_data: false, // Hopper-to-Hopper clandestine streams are never remotely killed
             data: encrypted_package.into(),
             sequence_number: None,
+            relayed_from_opt: None,
         };
 
         debug!(
@@ -132,6 +134,7 @@ mod tests {
     use super::*;
     use crate::blockchain::blockchain_interface::contract_address;
     use crate::sub_lib::cryptde::PublicKey;
+    use crate::sub_lib::cryptde_null::CryptDENull;
     use crate::sub_lib::dispatcher::{Component, InboundClientData};
     use crate::sub_lib::node_addr::NodeAddr;
     use crate::sub_lib::route::Route;
@@ -146,6 +149,15 @@ mod tests {
     use std::net::{IpAddr, Ipv4Addr};
     use std::str::FromStr;
 
+    // Every package is stamped differently, so only its contents can be compared
+    fn decode_for(key: &PublicKey, data: &[u8]) -> LiveCoresPackage {
+        LiveCoresPackage::decode(
+            &CryptDENull::from(key, DEFAULT_CHAIN_ID),
+            &CryptData::new(data),
+        )
+        .unwrap()
+    }
+
     #[test]
     fn converts_no_lookup_incipient_message_to_live_and_sends_to_dispatcher() {
         let (dispatcher, _, dispatcher_recording_arc) = make_recorder();
@@ -174,14 +186,12 @@ mod tests {
         let transmit_data_msg = dispatcher_recording.get_record::<TransmitDataMsg>(0);
         let (lcp, _) = LiveCoresPackage::from_no_lookup_incipient(package, main_cryptde()).unwrap();
         assert_eq!(
-            &TransmitDataMsg {
-                endpoint: Endpoint::Socket(SocketAddr::from_str("1.2.1.2:1212").unwrap()),
-                last_data: false,
-                sequence_number: None,
-                data: encodex(main_cryptde(), &target_key, &lcp).unwrap().into(),
-            },
-            transmit_data_msg
+            transmit_data_msg.endpoint,
+            Endpoint::Socket(SocketAddr::from_str("1.2.1.2:1212").unwrap())
         );
+        assert_eq!(transmit_data_msg.last_data, false);
+        assert_eq!(transmit_data_msg.sequence_number, None);
+        assert_eq!(decode_for(&target_key, &transmit_data_msg.data), lcp);
     }
 
     #[test]
@@ -244,16 +254,10 @@ mod tests {
         let record = dispatcher_recording.get_record::<TransmitDataMsg>(0);
         let (expected_lcp, _) =
             LiveCoresPackage::from_incipient(incipient_cores_package, cryptde).unwrap();
-        let expected_lcp_enc = encodex(cryptde, &destination_key, &expected_lcp).unwrap();
-        assert_eq!(
-            TransmitDataMsg {
-                endpoint: Endpoint::Key(destination_key.clone()),
-                last_data: false,
-                sequence_number: None,
-                data: expected_lcp_enc.into(),
-            },
-            *record,
-        );
+        assert_eq!(record.endpoint, Endpoint::Key(destination_key.clone()));
+        assert_eq!(record.last_data, false);
+        assert_eq!(record.sequence_number, None);
+        assert_eq!(decode_for(&destination_key, &record.data), expected_lcp);
     }
 
     #[test]
@@ -291,7 +295,6 @@ mod tests {
         let record = hopper_recording.get_record::<InboundClientData>(0);
         let (expected_lcp, _) =
             LiveCoresPackage::from_incipient(incipient_cores_package, cryptde).unwrap();
-        let expected_lcp_enc = encodex(cryptde, &destination_key, &expected_lcp).unwrap();
         assert_eq!(
             InboundClientData {
                 peer_addr: SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 0),
@@ -299,10 +302,11 @@ mod tests {
                 last_data: false,
                 is_clandestine: true,
                 sequence_number: None,
-                data: expected_lcp_enc.into(),
+                data: record.data.clone(),
             },
             *record,
         );
+        assert_eq!(decode_for(destination_key, &record.data), expected_lcp);
     }
 
     #[test]
@@ -1,13 +1,15 @@
 // Copyright (c) 2017-2019, Substratum LLC (https://substratum.net) and/or its affiliates. All rights reserved.
 
 use crate::sub_lib::cryptde::PublicKey;
-use crate::sub_lib::cryptde::{decodex, CryptDE};
-use crate::sub_lib::cryptde::{CodexError, CryptData};
+use crate::sub_lib::cryptde::{encodex, CryptDE};
+use crate::sub_lib::cryptde::{CodexError, CryptData, PlainData, SerdeCborError};
 use crate::sub_lib::data_version::DataVersion;
-use crate::sub_lib::hop::LiveHop;
+use crate::sub_lib::deterministic;
+use crate::sub_lib::hop::{HopTiming, LiveHop};
 use crate::sub_lib::hopper::IncipientCoresPackage;
 use crate::sub_lib::hopper::{ExpiredCoresPackage, MessageType, NoLookupIncipientCoresPackage};
 use crate::sub_lib::route::Route;
+use crate::sub_lib::utils::{millis_timestamp, time_t_timestamp};
 use serde_derive::{Deserialize, Serialize};
 use std::net::SocketAddr;
 
@@ -16,8 +18,52 @@ pub struct LiveCoresPackage {
     pub version: DataVersion,
     pub route: Route,
     pub payload: CryptData,
+    // HopTimings, each encrypted to the timing key in the hop of the Node that added it
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hop_timings: Vec<CryptData>,
 }
 
+// When a package was sent to us, and a number it was sent with that no other package will have,
+// so that a copy of it can be recognized and refused
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PackageStamp {
+    pub timestamp: u32,
+    pub nonce: u64,
+}
+
+// Same layout as LiveCoresPackage, but the payload stays in the decrypted buffer. Packages from
+// Nodes too old to stamp them have no timestamp or nonce.
+#[derive(Deserialize)]
+struct LiveCoresPackageView<'a> {
+    version: DataVersion,
+    route: Route,
+    #[serde(borrow)]
+    payload: &'a [u8],
+    #[serde(default)]
+    timestamp: u32,
+    #[serde(default)]
+    nonce: u64,
+    #[serde(default)]
+    hop_timings: Vec<CryptData>,
+}
+
+// LiveCoresPackage with a fresh stamp, for each hop to put on the wire. Older Nodes ignore the
+// stamp.
+#[derive(Serialize)]
+struct StampedLiveCoresPackage<'a> {
+    version: &'a DataVersion,
+    route: &'a Route,
+    payload: &'a CryptData,
+    timestamp: u32,
+    nonce: u64,
+    #[serde(skip_serializing_if = "<[CryptData]>::is_empty")]
+    hop_timings: &'a [CryptData],
+}
+
+// CBOR map header, field names and length prefixes: comfortably more than a real package needs
+const SERIALIZATION_OVERHEAD: usize = 96;
+const SERIALIZATION_OVERHEAD_PER_HOP: usize = 9;
+
 impl LiveCoresPackage {
     pub fn version() -> DataVersion {
         DataVersion::new(0, 0).expect("Internal Error")
@@ -28,6 +74,80 @@ impl LiveCoresPackage {
             version: Self::version(),
             route,
             payload,
+            hop_timings: vec![],
+        }
+    }
+
+    // Relay Nodes decode and re-encode every package they route, so both directions avoid
+    // copying the payload more than the encryption itself requires. The payload of a decoded
+    // package reuses the allocation of the decrypted buffer: it's only moved to the front of
+    // that buffer rather than copied into a new one.
+    pub fn decode(cryptde: &dyn CryptDE, data: &CryptData) -> Result<LiveCoresPackage, CodexError> {
+        Self::decode_stamped(cryptde, data).map(|(package, _)| package)
+    }
+
+    pub fn decode_stamped(
+        cryptde: &dyn CryptDE,
+        data: &CryptData,
+    ) -> Result<(LiveCoresPackage, Option<PackageStamp>), CodexError> {
+        let decrypted = match cryptde.decode(data) {
+            Ok(d) => d,
+            Err(e) => return Err(CodexError::DecryptionError(e)),
+        };
+        let mut buffer: Vec<u8> = decrypted.into();
+        let (version, route, payload_start, payload_len, stamp_opt, hop_timings) =
+            match serde_cbor::de::from_slice::<LiveCoresPackageView>(&buffer) {
+                Ok(view) => (
+                    view.version,
+                    view.route,
+                    view.payload.as_ptr() as usize - buffer.as_ptr() as usize,
+                    view.payload.len(),
+                    match view.nonce {
+                        0 => None,
+                        nonce => Some(PackageStamp {
+                            timestamp: view.timestamp,
+                            nonce,
+                        }),
+                    },
+                    view.hop_timings,
+                ),
+                Err(e) => return Err(CodexError::DeserializationError(SerdeCborError::new(e))),
+            };
+        buffer.truncate(payload_start + payload_len);
+        buffer.drain(..payload_start);
+        Ok((
+            LiveCoresPackage {
+                version,
+                route,
+                payload: CryptData::from(buffer),
+                hop_timings,
+            },
+            stamp_opt,
+        ))
+    }
+
+    // Produces what encodex() would, plus a fresh stamp, but serializes into a buffer that's big
+    // enough from the start instead of growing it (and copying the payload again) along the way.
+    pub fn encode(
+        &self,
+        cryptde: &dyn CryptDE,
+        public_key: &PublicKey,
+    ) -> Result<CryptData, CodexError> {
+        let stamped = StampedLiveCoresPackage {
+            version: &self.version,
+            route: &self.route,
+            payload: &self.payload,
+            timestamp: time_t_timestamp(),
+            nonce: deterministic::random_u64("package nonce").max(1),
+            hop_timings: &self.hop_timings,
+        };
+        let mut serialized = Vec::with_capacity(self.serialized_size_hint());
+        if let Err(e) = serde_cbor::ser::to_writer(&mut serialized, &stamped) {
+            return Err(CodexError::SerializationError(SerdeCborError::new(e)));
+        }
+        match cryptde.encode(public_key, &PlainData::from(serialized)) {
+            Ok(c) => Ok(c),
+            Err(e) => Err(CodexError::EncryptionError(e)),
         }
     }
 
@@ -36,10 +156,27 @@ impl LiveCoresPackage {
         cryptde: &dyn CryptDE, // must be the main CryptDE of the Node to which the top hop is encrypted
     ) -> Result<(LiveHop, LiveCoresPackage), CodexError> {
         let next_hop = self.route.shift(cryptde)?;
-        let next_live = LiveCoresPackage::new(self.route, self.payload);
+        let mut next_live = LiveCoresPackage::new(self.route, self.payload);
+        next_live.hop_timings = self.hop_timings;
         Ok((next_hop, next_live))
     }
 
+    // Called by the Node that read hop just before it sends the package on, if the originator
+    // asked it for the time
+    pub fn stamp_hop_timing(&mut self, cryptde: &dyn CryptDE, hop: &LiveHop) {
+        let timing_key = match &hop.timing_key_opt {
+            Some(key) => key,
+            None => return,
+        };
+        let hop_timing = HopTiming {
+            public_key: cryptde.public_key().clone(),
+            sent_at_ms: millis_timestamp(),
+        };
+        if let Ok(stamp) = encodex(cryptde, timing_key, &hop_timing) {
+            self.hop_timings.push(stamp);
+        }
+    }
+
     pub fn from_no_lookup_incipient(
         no_lookup_incipient: NoLookupIncipientCoresPackage,
         cryptde: &dyn CryptDE, // must be the CryptDE of the Node the package is about to leave
@@ -69,6 +206,21 @@ impl LiveCoresPackage {
         Ok((LiveCoresPackage::new(route, incipient.payload), next_hop))
     }
 
+    fn serialized_size_hint(&self) -> usize {
+        let route_size: usize = self
+            .route
+            .hops
+            .iter()
+            .map(|hop| hop.len() + SERIALIZATION_OVERHEAD_PER_HOP)
+            .sum();
+        let timings_size: usize = self
+            .hop_timings
+            .iter()
+            .map(|timing| timing.len() + SERIALIZATION_OVERHEAD_PER_HOP)
+            .sum();
+        SERIALIZATION_OVERHEAD + route_size + timings_size + self.payload.len()
+    }
+
     pub fn to_expired(
         &self,
         immediate_neighbor_addr: SocketAddr,
@@ -76,18 +228,26 @@ impl LiveCoresPackage {
         payload_cryptde: &dyn CryptDE, // Must be the main or alias CryptDE of the Node for which the payload is intended.
     ) -> Result<ExpiredCoresPackage<MessageType>, CodexError> {
         let top_hop = self.route.next_hop(main_cryptde)?;
-        match decodex::<MessageType>(payload_cryptde, &self.payload).map(|decoded_payload| {
-            ExpiredCoresPackage::new(
-                immediate_neighbor_addr,
-                top_hop.payer.map(|p| p.wallet),
-                self.route.clone(),
-                decoded_payload,
-                self.payload.len(),
-            )
-        }) {
-            Ok(ecp) => Ok(ecp),
-            Err(e) => Err(e),
-        }
+        let decrypted = payload_cryptde
+            .decode(&self.payload)
+            .map_err(CodexError::DecryptionError)?;
+        let decoded_payload = match serde_cbor::de::from_slice::<MessageType>(decrypted.as_slice())
+        {
+            Ok(decoded_payload) => decoded_payload,
+            Err(e) => {
+                return Err(match MessageType::unrecognized_name(decrypted.as_slice()) {
+                    Some(name) => CodexError::UnrecognizedMessageType(name),
+                    None => CodexError::DeserializationError(SerdeCborError::new(e)),
+                })
+            }
+        };
+        Ok(ExpiredCoresPackage::new(
+            immediate_neighbor_addr,
+            top_hop.payer.map(|p| p.wallet),
+            self.route.clone(),
+            decoded_payload,
+            self.payload.len(),
+        ))
     }
 }
 
@@ -95,7 +255,7 @@ impl LiveCoresPackage {
 mod tests {
     use super::*;
     use crate::blockchain::blockchain_interface::contract_address;
-    use crate::sub_lib::cryptde::encodex;
+    use crate::sub_lib::cryptde::decodex;
     use crate::sub_lib::cryptde::PlainData;
     use crate::sub_lib::cryptde_null::CryptDENull;
     use crate::sub_lib::dispatcher::Component;
@@ -262,7 +422,8 @@ mod tests {
             LiveHop {
                 public_key: key34.clone(),
                 payer: Some(paying_wallet.as_payer(&key12, &contract_address)),
-                component: Component::Hopper
+                component: Component::Hopper,
+                timing_key_opt: None,
             },
             next_stop
         );
@@ -403,6 +564,51 @@ mod tests {
         );
     }
 
+    #[test]
+    fn to_expired_names_message_types_from_newer_nodes() {
+        #[derive(Serialize)]
+        enum FutureMessageType {
+            Teleport(Vec<u8>),
+        }
+        let cryptde = main_cryptde();
+        let route = Route::one_way(
+            RouteSegment::new(
+                vec![cryptde.public_key(), &PublicKey::new(b"next")],
+                Component::ProxyClient,
+            ),
+            cryptde,
+            None,
+            None,
+        )
+        .unwrap();
+        let future = LiveCoresPackage::new(
+            route.clone(),
+            encodex(
+                cryptde,
+                cryptde.public_key(),
+                &FutureMessageType::Teleport(vec![1, 2, 3]),
+            )
+            .unwrap(),
+        );
+        let corrupt = LiveCoresPackage::new(
+            route,
+            encodex(cryptde, cryptde.public_key(), &"not a MessageType").unwrap(),
+        );
+        let immediate_neighbor = SocketAddr::from_str("1.2.3.4:1234").unwrap();
+
+        let future_result = future.to_expired(immediate_neighbor, cryptde, cryptde);
+        let corrupt_result = corrupt.to_expired(immediate_neighbor, cryptde, cryptde);
+
+        assert_eq!(
+            future_result,
+            Err(CodexError::UnrecognizedMessageType("Teleport".to_string()))
+        );
+        match corrupt_result {
+            Err(CodexError::DeserializationError(_)) => (),
+            x => panic!("Expected DeserializationError; got {:?}", x),
+        }
+    }
+
     #[test]
     fn live_cores_package_serialization_deserialization() {
         let original =
@@ -414,4 +620,100 @@ mod tests {
 
         assert_eq!(deserialized, original);
     }
+
+    #[test]
+    fn encode_produces_what_older_nodes_can_decode() {
+        let cryptde = main_cryptde();
+        let subject = LiveCoresPackage::new(make_meaningless_route(), CryptData::new(&[7; 1000]));
+
+        let result = subject.encode(cryptde, cryptde.public_key()).unwrap();
+
+        assert_eq!(
+            decodex::<LiveCoresPackage>(cryptde, &result).unwrap(),
+            subject
+        );
+    }
+
+    #[test]
+    fn encode_stamps_each_package_differently() {
+        let cryptde = main_cryptde();
+        let subject = LiveCoresPackage::new(make_meaningless_route(), CryptData::new(&[7; 10]));
+        let before = time_t_timestamp();
+
+        let first = subject.encode(cryptde, cryptde.public_key()).unwrap();
+        let second = subject.encode(cryptde, cryptde.public_key()).unwrap();
+
+        let after = time_t_timestamp();
+        let (_, first_stamp) = LiveCoresPackage::decode_stamped(cryptde, &first).unwrap();
+        let (_, second_stamp) = LiveCoresPackage::decode_stamped(cryptde, &second).unwrap();
+        let (first_stamp, second_stamp) = (first_stamp.unwrap(), second_stamp.unwrap());
+        assert!(first_stamp.timestamp >= before && first_stamp.timestamp <= after);
+        assert_ne!(first_stamp.nonce, second_stamp.nonce);
+    }
+
+    #[test]
+    fn packages_from_older_nodes_have_no_stamp() {
+        let cryptde = main_cryptde();
+        let subject = LiveCoresPackage::new(make_meaningless_route(), CryptData::new(&[7; 10]));
+        let encoded = encodex(cryptde, cryptde.public_key(), &subject).unwrap();
+
+        let result = LiveCoresPackage::decode_stamped(cryptde, &encoded).unwrap();
+
+        assert_eq!(result, (subject, None));
+    }
+
+    #[test]
+    fn decode_reverses_encode() {
+        let cryptde = main_cryptde();
+        let payload = (0..5000).map(|n| n as u8).collect::<Vec<u8>>();
+        let original = LiveCoresPackage::new(make_meaningless_route(), CryptData::from(payload));
+        let encoded = original.encode(cryptde, cryptde.public_key()).unwrap();
+
+        let result = LiveCoresPackage::decode(cryptde, &encoded).unwrap();
+
+        assert_eq!(result, original);
+        assert_eq!(
+            result,
+            decodex::<LiveCoresPackage>(cryptde, &encoded).unwrap()
+        );
+    }
+
+    #[test]
+    fn hop_timings_are_stamped_only_when_asked_for_and_survive_the_trip_to_the_next_node() {
+        let cryptde = main_cryptde();
+        let timing_key = PublicKey::new(&[84, 84, 84]);
+        let timing_cryptde = CryptDENull::from(&timing_key, DEFAULT_CHAIN_ID);
+        let mut subject = LiveCoresPackage::new(make_meaningless_route(), CryptData::new(&[7; 10]));
+        let untimed_hop = LiveHop::new(&PublicKey::new(&[1]), None, Component::Hopper);
+        let timed_hop = untimed_hop.clone().with_timing_key(&timing_key);
+        let before = millis_timestamp();
+
+        subject.stamp_hop_timing(cryptde, &untimed_hop);
+        subject.stamp_hop_timing(cryptde, &timed_hop);
+
+        assert_eq!(subject.hop_timings.len(), 1);
+        let encoded = subject.encode(cryptde, cryptde.public_key()).unwrap();
+        let decoded = LiveCoresPackage::decode(cryptde, &encoded).unwrap();
+        let first_cryptde = CryptDENull::from(&PublicKey::new(b"ooga"), DEFAULT_CHAIN_ID);
+        let (_, next_live) = decoded.into_next_live(&first_cryptde).unwrap();
+        assert_eq!(next_live.hop_timings, subject.hop_timings);
+        let hop_timing = decodex::<HopTiming>(&timing_cryptde, &next_live.hop_timings[0]).unwrap();
+        assert_eq!(&hop_timing.public_key, cryptde.public_key());
+        assert!(hop_timing.sent_at_ms >= before);
+    }
+
+    #[test]
+    fn decode_complains_about_data_that_is_not_a_package() {
+        let cryptde = main_cryptde();
+        let not_a_package = cryptde
+            .encode(cryptde.public_key(), &PlainData::new(b"booga"))
+            .unwrap();
+
+        let result = LiveCoresPackage::decode(cryptde, &not_a_package);
+
+        match result {
+            Err(CodexError::DeserializationError(_)) => (),
+            x => panic!("Expected DeserializationError, got {:?}", x),
+        }
+    }
 }
@@ -2,6 +2,7 @@
 
 mod consuming_service;
 pub mod live_cores_package;
+mod replay_guard;
 mod routing_service;
 
 use crate::hopper::routing_service::RoutingServiceSubs;
@@ -55,6 +56,10 @@ impl Handler<BindMessage> for Hopper {
                 hopper_subs: msg.peer_actors.hopper,
                 to_dispatcher: msg.peer_actors.dispatcher.from_dispatcher_client,
                 to_accountant_routing: msg.peer_actors.accountant.report_routing_service_provided,
+                to_accountant_payment_overdue: msg
+                    .peer_actors
+                    .accountant
+                    .payment_overdue_from_hopper,
             },
             self.per_routing_service,
             self.per_routing_byte,
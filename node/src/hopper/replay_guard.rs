@@ -0,0 +1,118 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use crate::hopper::live_cores_package::PackageStamp;
+use crate::sub_lib::clock_skew;
+use std::collections::HashMap;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReplayVerdict {
+    Fresh,
+    // From a Node too old to stamp its packages; there's no telling, so it's let through
+    Unstamped,
+    Replayed,
+    Stale,
+}
+
+// Remembers the nonce of every stamped package it has let through until that package's timestamp
+// falls out of the window, after which a replay of it would be refused as stale anyway
+pub struct ReplayGuard {
+    window_secs: u32,
+    expirations: HashMap<u64, u32>,
+    last_pruned: u32,
+}
+
+// How far a package's timestamp may be from our clock, either way, before it's refused as stale,
+// is however far apart --clock-skew-tolerance says two clocks may be
+impl Default for ReplayGuard {
+    fn default() -> Self {
+        Self::new(clock_skew::tolerance_secs())
+    }
+}
+
+impl ReplayGuard {
+    pub fn new(window_secs: u32) -> ReplayGuard {
+        ReplayGuard {
+            window_secs,
+            expirations: HashMap::new(),
+            last_pruned: 0,
+        }
+    }
+
+    pub fn check(&mut self, stamp_opt: Option<PackageStamp>, now: u32) -> ReplayVerdict {
+        if now > self.last_pruned {
+            self.expirations.retain(|_, expiration| *expiration >= now);
+            self.last_pruned = now;
+        }
+        let stamp = match stamp_opt {
+            Some(stamp) => stamp,
+            None => return ReplayVerdict::Unstamped,
+        };
+        if stamp.timestamp.saturating_add(self.window_secs) < now
+            || stamp.timestamp > now.saturating_add(self.window_secs)
+        {
+            return ReplayVerdict::Stale;
+        }
+        if self.expirations.contains_key(&stamp.nonce) {
+            return ReplayVerdict::Replayed;
+        }
+        self.expirations.insert(
+            stamp.nonce,
+            stamp.timestamp.saturating_add(self.window_secs),
+        );
+        ReplayVerdict::Fresh
+    }
+
+    #[cfg(test)]
+    pub fn remembered(&self) -> usize {
+        self.expirations.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stamp(timestamp: u32, nonce: u64) -> Option<PackageStamp> {
+        Some(PackageStamp { timestamp, nonce })
+    }
+
+    #[test]
+    fn a_package_is_let_through_once() {
+        let mut subject = ReplayGuard::new(10);
+
+        assert_eq!(subject.check(stamp(1000, 1), 1000), ReplayVerdict::Fresh);
+        assert_eq!(subject.check(stamp(1000, 2), 1001), ReplayVerdict::Fresh);
+        assert_eq!(subject.check(stamp(1000, 1), 1005), ReplayVerdict::Replayed);
+    }
+
+    #[test]
+    fn packages_stamped_outside_the_window_are_stale() {
+        let mut subject = ReplayGuard::new(10);
+
+        assert_eq!(subject.check(stamp(989, 1), 1000), ReplayVerdict::Stale);
+        assert_eq!(subject.check(stamp(990, 2), 1000), ReplayVerdict::Fresh);
+        assert_eq!(subject.check(stamp(1010, 3), 1000), ReplayVerdict::Fresh);
+        assert_eq!(subject.check(stamp(1011, 4), 1000), ReplayVerdict::Stale);
+    }
+
+    #[test]
+    fn unstamped_packages_are_let_through_every_time() {
+        let mut subject = ReplayGuard::new(10);
+
+        assert_eq!(subject.check(None, 1000), ReplayVerdict::Unstamped);
+        assert_eq!(subject.check(None, 1000), ReplayVerdict::Unstamped);
+        assert_eq!(subject.remembered(), 0);
+    }
+
+    #[test]
+    fn nonces_are_forgotten_once_their_packages_would_be_stale() {
+        let mut subject = ReplayGuard::new(10);
+        subject.check(stamp(1000, 1), 1000);
+        subject.check(stamp(1005, 2), 1005);
+
+        assert_eq!(subject.check(stamp(1000, 1), 1011), ReplayVerdict::Stale);
+        assert_eq!(subject.remembered(), 1);
+        assert_eq!(subject.check(stamp(1005, 2), 1015), ReplayVerdict::Replayed);
+        subject.check(None, 1016);
+        assert_eq!(subject.remembered(), 0);
+    }
+}
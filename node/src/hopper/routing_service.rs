@@ -1,21 +1,32 @@
 // Copyright (c) 2017-2019, Substratum LLC (https://substratum.net) and/or its affiliates. All rights reserved.
 use super::live_cores_package::LiveCoresPackage;
+use super::replay_guard::{ReplayGuard, ReplayVerdict};
 use crate::blockchain::payer::Payer;
 use crate::neighborhood::gossip::Gossip_0v1;
-use crate::sub_lib::accountant::ReportRoutingServiceProvidedMessage;
-use crate::sub_lib::cryptde::{decodex, encodex, CodexError, CryptDE, CryptData, CryptdecError};
+use crate::sub_lib::accountant::{PaymentOverdue_0v1, ReportRoutingServiceProvidedMessage};
+use crate::sub_lib::cryptde::{CodexError, CryptDE, CryptData, CryptdecError};
+use crate::sub_lib::data_cap;
+use crate::sub_lib::data_cap::DataCapStatus;
 use crate::sub_lib::dispatcher::{Component, Endpoint, InboundClientData};
 use crate::sub_lib::hop::LiveHop;
 use crate::sub_lib::hopper::{ExpiredCoresPackage, HopperSubs, MessageType};
 use crate::sub_lib::logger::Logger;
-use crate::sub_lib::neighborhood::{GossipFailure_0v1, NeighborhoodSubs};
+use crate::sub_lib::metrics;
+use crate::sub_lib::neighborhood::{
+    Benchmark_0v1, GossipFailure_0v1, HolePunch_0v1, HopTimingsMessage, NeighborhoodSubs,
+};
 use crate::sub_lib::proxy_client::{
-    ClientResponsePayload_0v1, DnsResolveFailure_0v1, ProxyClientSubs,
+    ClientResponsePayload_0v1, ClientUdpResponsePayload_0v1, DnsResolveFailure_0v1, ProxyClientSubs,
+};
+use crate::sub_lib::proxy_server::{
+    ClientRequestPayload_0v1, ClientUdpRequestPayload_0v1, ProxyServerSubs, StreamAckPayload_0v1,
 };
-use crate::sub_lib::proxy_server::{ClientRequestPayload_0v1, ProxyServerSubs};
 use crate::sub_lib::stream_handler_pool::TransmitDataMsg;
+use crate::sub_lib::utils::{millis_timestamp, time_t_timestamp};
+use crate::sub_lib::wallet::Wallet;
 use actix::Recipient;
 use std::borrow::Borrow;
+use std::cell::RefCell;
 use std::convert::TryFrom;
 use std::net::SocketAddr;
 
@@ -26,6 +37,7 @@ pub struct RoutingServiceSubs {
     pub hopper_subs: HopperSubs,
     pub to_dispatcher: Recipient<TransmitDataMsg>,
     pub to_accountant_routing: Recipient<ReportRoutingServiceProvidedMessage>,
+    pub to_accountant_payment_overdue: Recipient<ExpiredCoresPackage<PaymentOverdue_0v1>>,
 }
 
 pub struct RoutingService {
@@ -36,6 +48,7 @@ pub struct RoutingService {
     per_routing_byte: u64,
     logger: Logger,
     is_decentralized: bool,
+    replay_guard: RefCell<ReplayGuard>,
 }
 
 impl RoutingService {
@@ -55,6 +68,7 @@ impl RoutingService {
             per_routing_byte,
             logger: Logger::new("RoutingService"),
             is_decentralized,
+            replay_guard: RefCell::new(ReplayGuard::default()),
         }
     }
 
@@ -70,20 +84,50 @@ impl RoutingService {
         let last_data = ibcd.last_data;
         let ibcd_but_data = ibcd.clone_but_data();
 
-        let live_package =
-            match decodex::<LiveCoresPackage>(self.main_cryptde, &CryptData::new(&ibcd.data[..])) {
-                Ok(lcp) => lcp,
-                Err(e) => {
-                    error!(
-                        self.logger,
-                        "Couldn't decode CORES package in {}-byte buffer from {}: {:?}",
-                        ibcd.data.len(),
-                        ibcd.peer_addr,
-                        e
-                    );
-                    return;
-                }
-            };
+        let (live_package, stamp_opt) = match LiveCoresPackage::decode_stamped(
+            self.main_cryptde,
+            &CryptData::from(ibcd.data),
+        ) {
+            Ok(decoded) => decoded,
+            Err(e) => {
+                error!(
+                    self.logger,
+                    "Couldn't decode CORES package in {}-byte buffer from {}: {:?}",
+                    data_size,
+                    peer_addr,
+                    e
+                );
+                return;
+            }
+        };
+        // A copy of a package we've already handled would be charged for and delivered again
+        match self
+            .replay_guard
+            .borrow_mut()
+            .check(stamp_opt, time_t_timestamp())
+        {
+            ReplayVerdict::Fresh | ReplayVerdict::Unstamped => (),
+            ReplayVerdict::Replayed => {
+                metrics::REPLAYED_PACKAGES.add(1);
+                warning!(
+                    self.logger,
+                    "Refusing {}-byte CORES package from {}: it's a copy of one already received",
+                    data_size,
+                    peer_addr
+                );
+                return;
+            }
+            ReplayVerdict::Stale => {
+                metrics::STALE_PACKAGES.add(1);
+                warning!(
+                    self.logger,
+                    "Refusing {}-byte CORES package from {}: its timestamp is too far from ours",
+                    data_size,
+                    peer_addr
+                );
+                return;
+            }
+        }
 
         let next_hop = match live_package.route.next_hop(self.main_cryptde.borrow()) {
             Ok(hop) => hop,
@@ -103,7 +147,7 @@ impl RoutingService {
         &self,
         sender_addr: SocketAddr,
         next_hop: LiveHop,
-        live_package: LiveCoresPackage,
+        mut live_package: LiveCoresPackage,
         last_data: bool,
         ibcd_but_data: &InboundClientData,
     ) {
@@ -114,7 +158,8 @@ impl RoutingService {
                 live_package.payload.len(),
                 next_hop.public_key
             );
-            self.route_data_externally(live_package, next_hop.payer, last_data);
+            live_package.stamp_hop_timing(self.main_cryptde, &next_hop);
+            self.route_data_externally(live_package, next_hop.payer, last_data, sender_addr);
         } else {
             debug!(
                 self.logger,
@@ -176,12 +221,9 @@ impl RoutingService {
                 return;
             }
         };
-        let payload = encodex(
-            self.main_cryptde,
-            &self.main_cryptde.public_key(),
-            &next_lcp,
-        )
-        .expect("Encryption of LiveCoresPackage failed");
+        let payload = next_lcp
+            .encode(self.main_cryptde, &self.main_cryptde.public_key())
+            .expect("Encryption of LiveCoresPackage failed");
         let inbound_client_data = InboundClientData {
             peer_addr: ibcd_but_data.peer_addr,
             reception_port: ibcd_but_data.reception_port,
@@ -204,6 +246,9 @@ impl RoutingService {
         live_package: LiveCoresPackage,
         payer_owns_secret_key: bool,
     ) {
+        if !live_package.hop_timings.is_empty() {
+            self.report_hop_timings(&live_package.hop_timings);
+        }
         let expired_package =
             match self.extract_expired_package(immediate_neighbor_addr, live_package) {
                 None => return,
@@ -217,6 +262,18 @@ impl RoutingService {
         self.route_expired_package(component, expired_package, payer_owns_secret_key)
     }
 
+    // Only the Neighborhood that asked for the timings holds the key to read them
+    fn report_hop_timings(&self, hop_timings: &[CryptData]) {
+        self.routing_service_subs
+            .neighborhood_subs
+            .hop_timings
+            .try_send(HopTimingsMessage {
+                timings: hop_timings.to_vec(),
+                received_at_ms: millis_timestamp(),
+            })
+            .expect("Neighborhood is dead")
+    }
+
     // TODO: Rather than trying both alias and main cryptdes, this method should accept the Component
     // that is to receive the package. If that Component is Neighborhood, it should use the main_cryptde
     // to expire it; if the Component is anything else, it should use the alias_cryptde.
@@ -239,6 +296,10 @@ impl RoutingService {
                     self.main_cryptde,
                 ) {
                     Ok(pkg) => pkg,
+                    Err(CodexError::UnrecognizedMessageType(name)) => {
+                        self.log_unrecognized_message_type(&name, data_len);
+                        return None;
+                    }
                     Err(CodexError::DecryptionError(CryptdecError::OpeningFailed)) => {
                         error!(
                             self.logger,
@@ -255,6 +316,10 @@ impl RoutingService {
                     }
                 }
             }
+            Err(CodexError::UnrecognizedMessageType(name)) => {
+                self.log_unrecognized_message_type(&name, data_len);
+                return None;
+            }
             Err(e) => {
                 error!(
                     self.logger,
@@ -268,6 +333,17 @@ impl RoutingService {
         Some(expired_package)
     }
 
+    // Not an error: Nodes of different versions share the network, and a newer one may send
+    // something this one doesn't know about yet
+    fn log_unrecognized_message_type(&self, name: &str, data_len: usize) {
+        info!(
+            self.logger,
+            "Ignoring CORES package with {}-byte {} payload: it's from a newer version of the Node",
+            data_len,
+            name
+        );
+    }
+
     fn route_expired_package(
         &self,
         component: Component,
@@ -299,18 +375,65 @@ impl RoutingService {
                         ))
                         .expect("ProxyClient is dead")
                 } else {
-                    let payload_len = &expired_package.payload_len;
-                    let address = match &expired_package.paying_wallet {
-                        Some(wallet) => format!("{} ", wallet),
-                        None => String::from(""),
+                    self.refuse_without_proof_of_payment(
+                        expired_package.payload_len,
+                        &expired_package.paying_wallet,
+                    );
+                }
+            }
+            (Component::ProxyClient, MessageType::ClientUdpRequest(vd)) => {
+                if !self.is_decentralized || payer_owns_secret_key {
+                    let client_request = match ClientUdpRequestPayload_0v1::try_from(vd) {
+                        Ok(curp) => curp,
+                        Err(e) => {
+                            error!(
+                                self.logger,
+                                "Received unmigratable ClientUdpRequestPayload: {:?}", e
+                            );
+                            return;
+                        }
                     };
-                    warning!(
-                        self.logger,
-                            "Refusing to route Expired CORES package with {}-byte payload without proof of {}paying wallet ownership.",
-                        payload_len, address
+                    self.routing_service_subs
+                        .proxy_client_subs
+                        .udp_from_hopper
+                        .try_send(ExpiredCoresPackage::new(
+                            expired_package.immediate_neighbor,
+                            expired_package.paying_wallet,
+                            expired_package.remaining_route,
+                            client_request,
+                            expired_package.payload_len,
+                        ))
+                        .expect("ProxyClient is dead")
+                } else {
+                    self.refuse_without_proof_of_payment(
+                        expired_package.payload_len,
+                        &expired_package.paying_wallet,
                     );
                 }
             }
+            (Component::ProxyClient, MessageType::StreamAck(vd)) => {
+                let stream_ack = match StreamAckPayload_0v1::try_from(vd) {
+                    Ok(sap) => sap,
+                    Err(e) => {
+                        error!(
+                            self.logger,
+                            "Received unmigratable StreamAckPayload: {:?}", e
+                        );
+                        return;
+                    }
+                };
+                self.routing_service_subs
+                    .proxy_client_subs
+                    .stream_ack_from_hopper
+                    .try_send(ExpiredCoresPackage::new(
+                        expired_package.immediate_neighbor,
+                        expired_package.paying_wallet,
+                        expired_package.remaining_route,
+                        stream_ack,
+                        expired_package.payload_len,
+                    ))
+                    .expect("ProxyClient is dead")
+            }
             (Component::ProxyServer, MessageType::ClientResponse(vd)) => {
                 let client_response = match ClientResponsePayload_0v1::try_from(vd) {
                     Ok(crp) => crp,
@@ -357,6 +480,48 @@ impl RoutingService {
                     ))
                     .expect("ProxyServer is dead")
             }
+            (Component::ProxyServer, MessageType::ClientUdpResponse(vd)) => {
+                let client_response = match ClientUdpResponsePayload_0v1::try_from(vd) {
+                    Ok(curp) => curp,
+                    Err(e) => {
+                        error!(
+                            self.logger,
+                            "Received unmigratable ClientUdpResponsePayload: {:?}", e
+                        );
+                        return;
+                    }
+                };
+                self.routing_service_subs
+                    .proxy_server_subs
+                    .udp_from_hopper
+                    .try_send(ExpiredCoresPackage::new(
+                        expired_package.immediate_neighbor,
+                        expired_package.paying_wallet,
+                        expired_package.remaining_route,
+                        client_response,
+                        expired_package.payload_len,
+                    ))
+                    .expect("ProxyServer is dead")
+            }
+            (Component::ProxyServer, MessageType::PaymentOverdue(vd)) => {
+                let notice = match PaymentOverdue_0v1::try_from(vd) {
+                    Ok(notice) => notice,
+                    Err(e) => {
+                        error!(self.logger, "Received unmigratable PaymentOverdue: {:?}", e);
+                        return;
+                    }
+                };
+                self.routing_service_subs
+                    .to_accountant_payment_overdue
+                    .try_send(ExpiredCoresPackage::new(
+                        expired_package.immediate_neighbor,
+                        expired_package.paying_wallet,
+                        expired_package.remaining_route,
+                        notice,
+                        expired_package.payload_len,
+                    ))
+                    .expect("Accountant is dead")
+            }
             (Component::Neighborhood, MessageType::Gossip(vd)) => {
                 let gossip = match Gossip_0v1::try_from(vd) {
                     Ok(g) => g,
@@ -397,6 +562,46 @@ impl RoutingService {
                     ))
                     .expect("Neighborhood is dead")
             }
+            (Component::Neighborhood, MessageType::HolePunch(vd)) => {
+                let hole_punch = match HolePunch_0v1::try_from(vd) {
+                    Ok(hp) => hp,
+                    Err(e) => {
+                        error!(self.logger, "Received unmigratable HolePunch: {:?}", e);
+                        return;
+                    }
+                };
+                self.routing_service_subs
+                    .neighborhood_subs
+                    .hole_punch
+                    .try_send(ExpiredCoresPackage::new(
+                        expired_package.immediate_neighbor,
+                        expired_package.paying_wallet,
+                        expired_package.remaining_route,
+                        hole_punch,
+                        expired_package.payload_len,
+                    ))
+                    .expect("Neighborhood is dead")
+            }
+            (Component::Neighborhood, MessageType::Benchmark(vd)) => {
+                let benchmark = match Benchmark_0v1::try_from(vd) {
+                    Ok(b) => b,
+                    Err(e) => {
+                        error!(self.logger, "Received unmigratable Benchmark: {:?}", e);
+                        return;
+                    }
+                };
+                self.routing_service_subs
+                    .neighborhood_subs
+                    .benchmark
+                    .try_send(ExpiredCoresPackage::new(
+                        expired_package.immediate_neighbor,
+                        expired_package.paying_wallet,
+                        expired_package.remaining_route,
+                        benchmark,
+                        expired_package.payload_len,
+                    ))
+                    .expect("Neighborhood is dead")
+            }
             (destination, payload) => error!(
                 self.logger,
                 "Attempt to send invalid combination {:?} to {:?}", payload, destination
@@ -404,11 +609,24 @@ impl RoutingService {
         };
     }
 
+    fn refuse_without_proof_of_payment(&self, payload_len: usize, paying_wallet: &Option<Wallet>) {
+        let address = match paying_wallet {
+            Some(wallet) => format!("{} ", wallet),
+            None => String::from(""),
+        };
+        warning!(
+            self.logger,
+            "Refusing to route Expired CORES package with {}-byte payload without proof of {}paying wallet ownership.",
+            payload_len, address
+        );
+    }
+
     fn route_data_externally(
         &self,
         live_package: LiveCoresPackage,
         payer: Option<Payer>,
         last_data: bool,
+        sender_addr: SocketAddr,
     ) {
         let payload_size = live_package.payload.len();
         match payer {
@@ -428,6 +646,14 @@ impl RoutingService {
                     );
                     return;
                 }
+                if data_cap::status() == DataCapStatus::Exhausted {
+                    warning!(
+                        self.logger,
+                        "This month's data cap is used up; electing not to route {}-byte payload further",
+                        payload_size
+                    );
+                    return;
+                }
                 match self.routing_service_subs.to_accountant_routing.try_send(
                     ReportRoutingServiceProvidedMessage {
                         paying_wallet: payer.wallet,
@@ -452,7 +678,7 @@ impl RoutingService {
             }
         }
 
-        let transmit_msg = match self.to_transmit_data_msg(live_package, last_data) {
+        let transmit_msg = match self.to_transmit_data_msg(live_package, last_data, sender_addr) {
             Ok(m) => m,
             Err(e) => {
                 error!(self.logger, "{:?}", e);
@@ -475,6 +701,7 @@ impl RoutingService {
         &self,
         live_package: LiveCoresPackage,
         last_data: bool,
+        sender_addr: SocketAddr,
     ) -> Result<TransmitDataMsg, CryptdecError> {
         let (next_hop, next_live_package) =
             match live_package.into_next_live(self.main_cryptde.borrow()) {
@@ -489,7 +716,7 @@ impl RoutingService {
                 Ok(p) => p,
             };
         let next_live_package_enc =
-            match encodex(self.main_cryptde, &next_hop.public_key, &next_live_package) {
+            match next_live_package.encode(self.main_cryptde, &next_hop.public_key) {
                 Ok(nlpe) => nlpe,
                 Err(e) => {
                     let msg = format!("Couldn't serialize or encrypt outgoing LCP: {:?}", e);
@@ -502,6 +729,8 @@ impl RoutingService {
             last_data,
             data: next_live_package_enc.into(),
             sequence_number: None,
+            // A package that comes around again from this Node's own Hopper is its own traffic
+            relayed_from_opt: Some(sender_addr.ip()).filter(|ip| !ip.is_loopback()),
         })
     }
 }
@@ -526,16 +755,25 @@ mod tests {
     use crate::test_utils::recorder::{make_recorder, peer_actors_builder};
     use crate::test_utils::{
         alias_cryptde, main_cryptde, make_meaningless_message_type, make_meaningless_stream_key,
-        make_paying_wallet, make_request_payload, make_response_payload, rate_pack_routing,
-        rate_pack_routing_byte, route_from_proxy_client, route_to_proxy_client,
+        make_paying_wallet, make_request_payload, make_response_payload, make_wallet,
+        rate_pack_routing, rate_pack_routing_byte, route_from_proxy_client, route_to_proxy_client,
         route_to_proxy_server,
     };
     use actix::System;
     use masq_lib::test_utils::environment_guard::EnvironmentGuard;
     use masq_lib::test_utils::utils::DEFAULT_CHAIN_ID;
-    use std::net::SocketAddr;
+    use std::net::{IpAddr, SocketAddr};
     use std::str::FromStr;
 
+    // Every package is stamped differently, so only its contents can be compared
+    fn decode_for(key: &PublicKey, data: &[u8]) -> LiveCoresPackage {
+        LiveCoresPackage::decode(
+            &CryptDENull::from(key, DEFAULT_CHAIN_ID),
+            &CryptData::new(data),
+        )
+        .unwrap()
+    }
+
     #[test]
     fn dns_resolution_failures_are_reported_to_the_proxy_server() {
         let main_cryptde = main_cryptde();
@@ -578,6 +816,7 @@ mod tests {
                 hopper_subs: peer_actors.hopper,
                 to_dispatcher: peer_actors.dispatcher.from_dispatcher_client,
                 to_accountant_routing: peer_actors.accountant.report_routing_service_provided,
+                to_accountant_payment_overdue: peer_actors.accountant.payment_overdue_from_hopper,
             },
             100,
             200,
@@ -594,6 +833,65 @@ mod tests {
         assert_eq!(dns_resolve_failure, message.payload);
     }
 
+    #[test]
+    fn payment_overdue_notices_are_reported_to_the_accountant() {
+        let main_cryptde = main_cryptde();
+        let alias_cryptde = alias_cryptde();
+        let route = route_to_proxy_server(&main_cryptde.public_key(), main_cryptde);
+        let notice = PaymentOverdue_0v1 {
+            creditor: make_wallet("creditor"),
+            debtor: make_wallet("debtor"),
+            balance: 1_234_567_890,
+            age_sec: 200_000,
+            ban_in_sec: 21_600,
+        };
+        let lcp = LiveCoresPackage::new(
+            route,
+            encodex(
+                alias_cryptde,
+                &alias_cryptde.public_key(),
+                &MessageType::PaymentOverdue(notice.clone().into()),
+            )
+            .unwrap(),
+        );
+        let data_enc = encodex(main_cryptde, &main_cryptde.public_key(), &lcp).unwrap();
+        let inbound_client_data = InboundClientData {
+            peer_addr: SocketAddr::from_str("1.2.3.4:5678").unwrap(),
+            reception_port: None,
+            sequence_number: None,
+            last_data: false,
+            is_clandestine: false,
+            data: data_enc.into(),
+        };
+        let (accountant, _, accountant_recording) = make_recorder();
+        let system = System::new("payment_overdue_notices_are_reported_to_the_accountant");
+        let peer_actors = peer_actors_builder().accountant(accountant).build();
+        let subject = RoutingService::new(
+            main_cryptde,
+            alias_cryptde,
+            RoutingServiceSubs {
+                proxy_client_subs: peer_actors.proxy_client,
+                proxy_server_subs: peer_actors.proxy_server,
+                neighborhood_subs: peer_actors.neighborhood,
+                hopper_subs: peer_actors.hopper,
+                to_dispatcher: peer_actors.dispatcher.from_dispatcher_client,
+                to_accountant_routing: peer_actors.accountant.report_routing_service_provided,
+                to_accountant_payment_overdue: peer_actors.accountant.payment_overdue_from_hopper,
+            },
+            100,
+            200,
+            false,
+        );
+
+        subject.route(inbound_client_data);
+
+        System::current().stop();
+        system.run();
+        let recordings = accountant_recording.lock().unwrap();
+        let message = recordings.get_record::<ExpiredCoresPackage<PaymentOverdue_0v1>>(0);
+        assert_eq!(message.payload, notice);
+    }
+
     #[test]
     fn logs_and_ignores_message_that_cannot_be_deserialized() {
         init_test_logging();
@@ -624,6 +922,7 @@ mod tests {
                 hopper_subs: peer_actors.hopper,
                 to_dispatcher: peer_actors.dispatcher.from_dispatcher_client,
                 to_accountant_routing: peer_actors.accountant.report_routing_service_provided,
+                to_accountant_payment_overdue: peer_actors.accountant.payment_overdue_from_hopper,
             },
             100,
             200,
@@ -637,6 +936,53 @@ mod tests {
         );
     }
 
+    #[test]
+    fn refuses_and_counts_a_copy_of_a_package_it_has_already_received() {
+        init_test_logging();
+        let main_cryptde = main_cryptde();
+        let alias_cryptde = alias_cryptde();
+        let route = route_from_proxy_client(&main_cryptde.public_key(), main_cryptde);
+        let lcp = LiveCoresPackage::new(
+            route,
+            encodex(main_cryptde, &main_cryptde.public_key(), &[42u8]).unwrap(),
+        );
+        let data_enc = lcp.encode(main_cryptde, main_cryptde.public_key()).unwrap();
+        let inbound_client_data = InboundClientData {
+            peer_addr: SocketAddr::from_str("1.2.3.5:5678").unwrap(),
+            reception_port: None,
+            sequence_number: None,
+            last_data: false,
+            is_clandestine: false,
+            data: data_enc.into(),
+        };
+        let peer_actors = peer_actors_builder().build();
+        let subject = RoutingService::new(
+            main_cryptde,
+            alias_cryptde,
+            RoutingServiceSubs {
+                proxy_client_subs: peer_actors.proxy_client,
+                proxy_server_subs: peer_actors.proxy_server,
+                neighborhood_subs: peer_actors.neighborhood,
+                hopper_subs: peer_actors.hopper,
+                to_dispatcher: peer_actors.dispatcher.from_dispatcher_client,
+                to_accountant_routing: peer_actors.accountant.report_routing_service_provided,
+                to_accountant_payment_overdue: peer_actors.accountant.payment_overdue_from_hopper,
+            },
+            100,
+            200,
+            false,
+        );
+        let replays_before = metrics::REPLAYED_PACKAGES.get();
+
+        subject.route(inbound_client_data.clone());
+        subject.route(inbound_client_data);
+
+        assert!(metrics::REPLAYED_PACKAGES.get() > replays_before);
+        TestLogHandler::new().exists_log_matching(
+            "Refusing \\d+-byte CORES package from 1.2.3.5:5678: it's a copy of one already received",
+        );
+    }
+
     #[test]
     fn logs_and_ignores_message_that_cannot_be_decrypted() {
         init_test_logging();
@@ -668,6 +1014,7 @@ mod tests {
                 hopper_subs: peer_actors.hopper,
                 to_dispatcher: peer_actors.dispatcher.from_dispatcher_client,
                 to_accountant_routing: peer_actors.accountant.report_routing_service_provided,
+                to_accountant_payment_overdue: peer_actors.accountant.payment_overdue_from_hopper,
             },
             100,
             200,
@@ -717,6 +1064,7 @@ mod tests {
                 hopper_subs: peer_actors.hopper,
                 to_dispatcher: peer_actors.dispatcher.from_dispatcher_client,
                 to_accountant_routing: peer_actors.accountant.report_routing_service_provided,
+                to_accountant_payment_overdue: peer_actors.accountant.payment_overdue_from_hopper,
             },
             100,
             200,
@@ -771,6 +1119,7 @@ mod tests {
                 hopper_subs: peer_actors.hopper,
                 to_dispatcher: peer_actors.dispatcher.from_dispatcher_client,
                 to_accountant_routing: peer_actors.accountant.report_routing_service_provided,
+                to_accountant_payment_overdue: peer_actors.accountant.payment_overdue_from_hopper,
             },
             0,
             0,
@@ -839,6 +1188,7 @@ mod tests {
                 hopper_subs: peer_actors.hopper,
                 to_dispatcher: peer_actors.dispatcher.from_dispatcher_client,
                 to_accountant_routing: peer_actors.accountant.report_routing_service_provided,
+                to_accountant_payment_overdue: peer_actors.accountant.payment_overdue_from_hopper,
             },
             0,
             0,
@@ -867,24 +1217,303 @@ mod tests {
     }
 
     #[test]
-    fn converts_live_gossip_message_to_expired_for_neighborhood() {
+    fn hop_timings_on_a_returning_package_are_reported_to_the_neighborhood() {
         let _eg = EnvironmentGuard::new();
         BAN_CACHE.clear();
         let main_cryptde = main_cryptde();
         let alias_cryptde = alias_cryptde();
-        let (component, _, component_recording_arc) = make_recorder();
-        let mut route = Route::one_way(
-            RouteSegment::new(
-                vec![&main_cryptde.public_key(), &main_cryptde.public_key()],
-                Component::Neighborhood,
-            ),
-            main_cryptde,
-            None,
-            None,
-        )
-        .unwrap();
-        route.shift(main_cryptde).unwrap();
-        let payload = GossipBuilder::empty();
+        let (proxy_server, _, proxy_server_recording_arc) = make_recorder();
+        let (neighborhood, _, neighborhood_recording_arc) = make_recorder();
+        let route = route_to_proxy_server(&main_cryptde.public_key(), main_cryptde);
+        let payload = make_response_payload(0, main_cryptde);
+        let mut lcp = LiveCoresPackage::new(
+            route,
+            encodex::<MessageType>(
+                alias_cryptde,
+                &alias_cryptde.public_key(),
+                &payload.clone().into(),
+            )
+            .unwrap(),
+        );
+        let timings = vec![CryptData::new(b"exit"), CryptData::new(b"relay")];
+        lcp.hop_timings = timings.clone();
+        let lcp_enc = encodex(main_cryptde, main_cryptde.public_key(), &lcp).unwrap();
+        let inbound_client_data = InboundClientData {
+            peer_addr: SocketAddr::from_str("1.3.2.4:5678").unwrap(),
+            reception_port: None,
+            last_data: false,
+            is_clandestine: true,
+            sequence_number: None,
+            data: lcp_enc.into(),
+        };
+        let system =
+            System::new("hop_timings_on_a_returning_package_are_reported_to_the_neighborhood");
+        let peer_actors = peer_actors_builder()
+            .proxy_server(proxy_server)
+            .neighborhood(neighborhood)
+            .build();
+        let subject = RoutingService::new(
+            main_cryptde,
+            alias_cryptde,
+            RoutingServiceSubs {
+                proxy_client_subs: peer_actors.proxy_client,
+                proxy_server_subs: peer_actors.proxy_server,
+                neighborhood_subs: peer_actors.neighborhood,
+                hopper_subs: peer_actors.hopper,
+                to_dispatcher: peer_actors.dispatcher.from_dispatcher_client,
+                to_accountant_routing: peer_actors.accountant.report_routing_service_provided,
+                to_accountant_payment_overdue: peer_actors.accountant.payment_overdue_from_hopper,
+            },
+            0,
+            0,
+            false,
+        );
+        let before = millis_timestamp();
+
+        subject.route(inbound_client_data);
+
+        let after = millis_timestamp();
+        System::current().stop();
+        system.run();
+        let neighborhood_recording = neighborhood_recording_arc.lock().unwrap();
+        let record = neighborhood_recording.get_record::<HopTimingsMessage>(0);
+        assert_eq!(record.timings, timings);
+        assert!(record.received_at_ms >= before && record.received_at_ms <= after);
+        let proxy_server_recording = proxy_server_recording_arc.lock().unwrap();
+        let record =
+            proxy_server_recording.get_record::<ExpiredCoresPackage<ClientResponsePayload_0v1>>(0);
+        assert_eq!(record.payload, payload);
+    }
+
+    #[test]
+    fn converts_live_udp_request_to_expired_for_proxy_client() {
+        let _eg = EnvironmentGuard::new();
+        BAN_CACHE.clear();
+        let main_cryptde = main_cryptde();
+        let alias_cryptde = alias_cryptde();
+        let (component, _, component_recording_arc) = make_recorder();
+        let route = route_to_proxy_client(&main_cryptde.public_key(), main_cryptde);
+        let payload = ClientUdpRequestPayload_0v1 {
+            stream_key: make_meaningless_stream_key(),
+            target_hostname: "example.com".to_string(),
+            target_port: 443,
+            data: PlainData::new(b"QUIC initial"),
+            originator_public_key: main_cryptde.public_key().clone(),
+        };
+        let lcp = LiveCoresPackage::new(
+            route,
+            encodex::<MessageType>(
+                main_cryptde,
+                &main_cryptde.public_key(),
+                &payload.clone().into(),
+            )
+            .unwrap(),
+        );
+        let lcp_a = lcp.clone();
+        let lcp_enc = encodex(main_cryptde, main_cryptde.public_key(), &lcp).unwrap();
+        let inbound_client_data = InboundClientData {
+            peer_addr: SocketAddr::from_str("1.2.3.4:5678").unwrap(),
+            reception_port: None,
+            sequence_number: None,
+            last_data: false,
+            is_clandestine: true,
+            data: lcp_enc.into(),
+        };
+        let system = System::new("converts_live_udp_request_to_expired_for_proxy_client");
+        let peer_actors = peer_actors_builder().proxy_client(component).build();
+        let subject = RoutingService::new(
+            main_cryptde,
+            alias_cryptde,
+            RoutingServiceSubs {
+                proxy_client_subs: peer_actors.proxy_client,
+                proxy_server_subs: peer_actors.proxy_server,
+                neighborhood_subs: peer_actors.neighborhood,
+                hopper_subs: peer_actors.hopper,
+                to_dispatcher: peer_actors.dispatcher.from_dispatcher_client,
+                to_accountant_routing: peer_actors.accountant.report_routing_service_provided,
+                to_accountant_payment_overdue: peer_actors.accountant.payment_overdue_from_hopper,
+            },
+            0,
+            0,
+            false,
+        );
+
+        subject.route(inbound_client_data);
+
+        System::current().stop();
+        system.run();
+        let component_recording = component_recording_arc.lock().unwrap();
+        let record =
+            component_recording.get_record::<ExpiredCoresPackage<ClientUdpRequestPayload_0v1>>(0);
+        let expected_ecp = lcp_a
+            .to_expired(
+                SocketAddr::from_str("1.2.3.4:5678").unwrap(),
+                main_cryptde,
+                main_cryptde,
+            )
+            .unwrap();
+        assert_eq!(record.remaining_route, expected_ecp.remaining_route);
+        assert_eq!(record.payload, payload);
+        assert_eq!(record.payload_len, expected_ecp.payload_len);
+    }
+
+    #[test]
+    fn converts_live_stream_ack_to_expired_for_proxy_client() {
+        let _eg = EnvironmentGuard::new();
+        BAN_CACHE.clear();
+        let main_cryptde = main_cryptde();
+        let alias_cryptde = alias_cryptde();
+        let (component, _, component_recording_arc) = make_recorder();
+        let route = route_to_proxy_client(&main_cryptde.public_key(), main_cryptde);
+        let payload = StreamAckPayload_0v1 {
+            stream_key: make_meaningless_stream_key(),
+            next_sequence_number: 1234,
+        };
+        let lcp = LiveCoresPackage::new(
+            route,
+            encodex::<MessageType>(
+                main_cryptde,
+                &main_cryptde.public_key(),
+                &payload.clone().into(),
+            )
+            .unwrap(),
+        );
+        let lcp_a = lcp.clone();
+        let lcp_enc = encodex(main_cryptde, main_cryptde.public_key(), &lcp).unwrap();
+        let inbound_client_data = InboundClientData {
+            peer_addr: SocketAddr::from_str("1.2.3.4:5678").unwrap(),
+            reception_port: None,
+            sequence_number: None,
+            last_data: false,
+            is_clandestine: true,
+            data: lcp_enc.into(),
+        };
+        let system = System::new("converts_live_stream_ack_to_expired_for_proxy_client");
+        let peer_actors = peer_actors_builder().proxy_client(component).build();
+        let subject = RoutingService::new(
+            main_cryptde,
+            alias_cryptde,
+            RoutingServiceSubs {
+                proxy_client_subs: peer_actors.proxy_client,
+                proxy_server_subs: peer_actors.proxy_server,
+                neighborhood_subs: peer_actors.neighborhood,
+                hopper_subs: peer_actors.hopper,
+                to_dispatcher: peer_actors.dispatcher.from_dispatcher_client,
+                to_accountant_routing: peer_actors.accountant.report_routing_service_provided,
+                to_accountant_payment_overdue: peer_actors.accountant.payment_overdue_from_hopper,
+            },
+            0,
+            0,
+            false,
+        );
+
+        subject.route(inbound_client_data);
+
+        System::current().stop();
+        system.run();
+        let component_recording = component_recording_arc.lock().unwrap();
+        let record = component_recording.get_record::<ExpiredCoresPackage<StreamAckPayload_0v1>>(0);
+        let expected_ecp = lcp_a
+            .to_expired(
+                SocketAddr::from_str("1.2.3.4:5678").unwrap(),
+                main_cryptde,
+                main_cryptde,
+            )
+            .unwrap();
+        assert_eq!(record.remaining_route, expected_ecp.remaining_route);
+        assert_eq!(record.payload, payload);
+        assert_eq!(record.payload_len, expected_ecp.payload_len);
+    }
+
+    #[test]
+    fn converts_live_udp_response_to_expired_for_proxy_server() {
+        let _eg = EnvironmentGuard::new();
+        BAN_CACHE.clear();
+        let main_cryptde = main_cryptde();
+        let alias_cryptde = alias_cryptde();
+        let (component, _, component_recording_arc) = make_recorder();
+        let route = route_to_proxy_server(&main_cryptde.public_key(), main_cryptde);
+        let payload = ClientUdpResponsePayload_0v1 {
+            stream_key: make_meaningless_stream_key(),
+            source: SocketAddr::from_str("4.3.2.1:443").unwrap(),
+            data: PlainData::new(b"QUIC handshake"),
+        };
+        let lcp = LiveCoresPackage::new(
+            route,
+            encodex::<MessageType>(
+                alias_cryptde,
+                &alias_cryptde.public_key(),
+                &payload.clone().into(),
+            )
+            .unwrap(),
+        );
+        let lcp_a = lcp.clone();
+        let lcp_enc = encodex(main_cryptde, main_cryptde.public_key(), &lcp).unwrap();
+        let inbound_client_data = InboundClientData {
+            peer_addr: SocketAddr::from_str("1.3.2.4:5678").unwrap(),
+            reception_port: None,
+            last_data: false,
+            is_clandestine: true,
+            sequence_number: None,
+            data: lcp_enc.into(),
+        };
+        let system = System::new("converts_live_udp_response_to_expired_for_proxy_server");
+        let peer_actors = peer_actors_builder().proxy_server(component).build();
+        let subject = RoutingService::new(
+            main_cryptde,
+            alias_cryptde,
+            RoutingServiceSubs {
+                proxy_client_subs: peer_actors.proxy_client,
+                proxy_server_subs: peer_actors.proxy_server,
+                neighborhood_subs: peer_actors.neighborhood,
+                hopper_subs: peer_actors.hopper,
+                to_dispatcher: peer_actors.dispatcher.from_dispatcher_client,
+                to_accountant_routing: peer_actors.accountant.report_routing_service_provided,
+                to_accountant_payment_overdue: peer_actors.accountant.payment_overdue_from_hopper,
+            },
+            0,
+            0,
+            false,
+        );
+
+        subject.route(inbound_client_data);
+
+        System::current().stop();
+        system.run();
+        let component_recording = component_recording_arc.lock().unwrap();
+        let record =
+            component_recording.get_record::<ExpiredCoresPackage<ClientUdpResponsePayload_0v1>>(0);
+        let expected_ecp = lcp_a
+            .to_expired(
+                SocketAddr::from_str("1.3.2.4:5678").unwrap(),
+                main_cryptde,
+                alias_cryptde,
+            )
+            .unwrap();
+        assert_eq!(record.remaining_route, expected_ecp.remaining_route);
+        assert_eq!(record.payload, payload);
+        assert_eq!(record.payload_len, expected_ecp.payload_len);
+    }
+
+    #[test]
+    fn converts_live_gossip_message_to_expired_for_neighborhood() {
+        let _eg = EnvironmentGuard::new();
+        BAN_CACHE.clear();
+        let main_cryptde = main_cryptde();
+        let alias_cryptde = alias_cryptde();
+        let (component, _, component_recording_arc) = make_recorder();
+        let mut route = Route::one_way(
+            RouteSegment::new(
+                vec![&main_cryptde.public_key(), &main_cryptde.public_key()],
+                Component::Neighborhood,
+            ),
+            main_cryptde,
+            None,
+            None,
+        )
+        .unwrap();
+        route.shift(main_cryptde).unwrap();
+        let payload = GossipBuilder::empty();
         let lcp = LiveCoresPackage::new(
             route,
             encodex::<MessageType>(
@@ -917,6 +1546,7 @@ mod tests {
                 hopper_subs: peer_actors.hopper,
                 to_dispatcher: peer_actors.dispatcher.from_dispatcher_client,
                 to_accountant_routing: peer_actors.accountant.report_routing_service_provided,
+                to_accountant_payment_overdue: peer_actors.accountant.payment_overdue_from_hopper,
             },
             0,
             0,
@@ -991,6 +1621,7 @@ mod tests {
                 hopper_subs: peer_actors.hopper,
                 to_dispatcher: peer_actors.dispatcher.from_dispatcher_client,
                 to_accountant_routing: peer_actors.accountant.report_routing_service_provided,
+                to_accountant_payment_overdue: peer_actors.accountant.payment_overdue_from_hopper,
             },
             0,
             0,
@@ -1017,6 +1648,153 @@ mod tests {
         assert_eq!(record.payload_len, expected_ecp.payload_len);
     }
 
+    #[test]
+    fn converts_live_hole_punch_message_to_expired_for_neighborhood() {
+        let _eg = EnvironmentGuard::new();
+        BAN_CACHE.clear();
+        let cryptde = main_cryptde();
+        let (component, _, component_recording_arc) = make_recorder();
+        let mut route = Route::one_way(
+            RouteSegment::new(
+                vec![&cryptde.public_key(), &cryptde.public_key()],
+                Component::Neighborhood,
+            ),
+            cryptde,
+            None,
+            None,
+        )
+        .unwrap();
+        route.shift(cryptde).unwrap();
+        let hole_punch = HolePunch_0v1::Request {
+            target: PublicKey::new(&b"target"[..]),
+            port: 4321,
+        };
+        let payload = MessageType::HolePunch(hole_punch.clone().into());
+        let lcp = LiveCoresPackage::new(
+            route,
+            encodex::<MessageType>(cryptde, &cryptde.public_key(), &payload).unwrap(),
+        );
+        let data_enc = encodex(cryptde, &cryptde.public_key(), &lcp).unwrap();
+        let inbound_client_data = InboundClientData {
+            peer_addr: SocketAddr::from_str("1.3.2.4:5678").unwrap(),
+            reception_port: None,
+            last_data: false,
+            is_clandestine: true,
+            sequence_number: None,
+            data: data_enc.into(),
+        };
+
+        let system = System::new("converts_live_hole_punch_message_to_expired_for_neighborhood");
+        let peer_actors = peer_actors_builder().neighborhood(component).build();
+        let subject = RoutingService::new(
+            cryptde,
+            alias_cryptde(),
+            RoutingServiceSubs {
+                proxy_client_subs: peer_actors.proxy_client,
+                proxy_server_subs: peer_actors.proxy_server,
+                neighborhood_subs: peer_actors.neighborhood,
+                hopper_subs: peer_actors.hopper,
+                to_dispatcher: peer_actors.dispatcher.from_dispatcher_client,
+                to_accountant_routing: peer_actors.accountant.report_routing_service_provided,
+                to_accountant_payment_overdue: peer_actors.accountant.payment_overdue_from_hopper,
+            },
+            0,
+            0,
+            true,
+        );
+
+        subject.route(inbound_client_data);
+
+        System::current().stop();
+        system.run();
+        let component_recording = component_recording_arc.lock().unwrap();
+        let record = component_recording.get_record::<ExpiredCoresPackage<HolePunch_0v1>>(0);
+        let expected_ecp = lcp
+            .to_expired(
+                SocketAddr::from_str("1.3.2.4:5678").unwrap(),
+                cryptde,
+                cryptde,
+            )
+            .unwrap();
+        assert_eq!(record.immediate_neighbor, expected_ecp.immediate_neighbor);
+        assert_eq!(record.paying_wallet, expected_ecp.paying_wallet);
+        assert_eq!(record.remaining_route, expected_ecp.remaining_route);
+        assert_eq!(record.payload, hole_punch);
+        assert_eq!(record.payload_len, expected_ecp.payload_len);
+    }
+
+    #[test]
+    fn converts_live_benchmark_message_to_expired_for_neighborhood() {
+        let _eg = EnvironmentGuard::new();
+        BAN_CACHE.clear();
+        let cryptde = main_cryptde();
+        let (component, _, component_recording_arc) = make_recorder();
+        let mut route = Route::one_way(
+            RouteSegment::new(
+                vec![&cryptde.public_key(), &cryptde.public_key()],
+                Component::Neighborhood,
+            ),
+            cryptde,
+            None,
+            None,
+        )
+        .unwrap();
+        route.shift(cryptde).unwrap();
+        let benchmark = Benchmark_0v1::Echo { nonce: 1234 };
+        let payload = MessageType::Benchmark(benchmark.clone().into());
+        let lcp = LiveCoresPackage::new(
+            route,
+            encodex::<MessageType>(cryptde, &cryptde.public_key(), &payload).unwrap(),
+        );
+        let data_enc = encodex(cryptde, &cryptde.public_key(), &lcp).unwrap();
+        let inbound_client_data = InboundClientData {
+            peer_addr: SocketAddr::from_str("1.3.2.4:5678").unwrap(),
+            reception_port: None,
+            last_data: false,
+            is_clandestine: true,
+            sequence_number: None,
+            data: data_enc.into(),
+        };
+
+        let system = System::new("converts_live_benchmark_message_to_expired_for_neighborhood");
+        let peer_actors = peer_actors_builder().neighborhood(component).build();
+        let subject = RoutingService::new(
+            cryptde,
+            alias_cryptde(),
+            RoutingServiceSubs {
+                proxy_client_subs: peer_actors.proxy_client,
+                proxy_server_subs: peer_actors.proxy_server,
+                neighborhood_subs: peer_actors.neighborhood,
+                hopper_subs: peer_actors.hopper,
+                to_dispatcher: peer_actors.dispatcher.from_dispatcher_client,
+                to_accountant_routing: peer_actors.accountant.report_routing_service_provided,
+                to_accountant_payment_overdue: peer_actors.accountant.payment_overdue_from_hopper,
+            },
+            0,
+            0,
+            true,
+        );
+
+        subject.route(inbound_client_data);
+
+        System::current().stop();
+        system.run();
+        let component_recording = component_recording_arc.lock().unwrap();
+        let record = component_recording.get_record::<ExpiredCoresPackage<Benchmark_0v1>>(0);
+        let expected_ecp = lcp
+            .to_expired(
+                SocketAddr::from_str("1.3.2.4:5678").unwrap(),
+                cryptde,
+                cryptde,
+            )
+            .unwrap();
+        assert_eq!(record.immediate_neighbor, expected_ecp.immediate_neighbor);
+        assert_eq!(record.paying_wallet, expected_ecp.paying_wallet);
+        assert_eq!(record.remaining_route, expected_ecp.remaining_route);
+        assert_eq!(record.payload, benchmark);
+        assert_eq!(record.payload_len, expected_ecp.payload_len);
+    }
+
     #[test]
     fn passes_on_inbound_client_data_not_meant_for_this_node() {
         let _eg = EnvironmentGuard::new();
@@ -1070,6 +1848,7 @@ mod tests {
                 hopper_subs: peer_actors.hopper,
                 to_dispatcher: peer_actors.dispatcher.from_dispatcher_client,
                 to_accountant_routing: peer_actors.accountant.report_routing_service_provided,
+                to_accountant_payment_overdue: peer_actors.accountant.payment_overdue_from_hopper,
             },
             rate_pack_routing(103),
             rate_pack_routing_byte(103),
@@ -1084,17 +1863,14 @@ mod tests {
         let dispatcher_recording = dispatcher_recording_arc.lock().unwrap();
         let record = dispatcher_recording.get_record::<TransmitDataMsg>(0);
         let expected_lcp = lcp_a.into_next_live(main_cryptde).unwrap().1;
-        let expected_lcp_ser = PlainData::new(&serde_cbor::ser::to_vec(&expected_lcp).unwrap());
-        let expected_lcp_enc = main_cryptde.encode(&next_key, &expected_lcp_ser).unwrap();
+        assert_eq!(record.endpoint, Endpoint::Key(next_key.clone()));
+        assert_eq!(record.last_data, true);
+        assert_eq!(record.sequence_number, None);
         assert_eq!(
-            *record,
-            TransmitDataMsg {
-                endpoint: Endpoint::Key(next_key.clone()),
-                last_data: true,
-                sequence_number: None,
-                data: expected_lcp_enc.into(),
-            }
+            record.relayed_from_opt,
+            Some(IpAddr::from_str("1.2.3.4").unwrap())
         );
+        assert_eq!(decode_for(&next_key, &record.data), expected_lcp);
         let accountant_recording = accountant_recording_arc.lock().unwrap();
         let message = accountant_recording.get_record::<ReportRoutingServiceProvidedMessage>(0);
         assert!(message.paying_wallet.congruent(&paying_wallet));
@@ -1159,6 +1935,7 @@ mod tests {
                 hopper_subs: peer_actors.hopper,
                 to_dispatcher: peer_actors.dispatcher.from_dispatcher_client,
                 to_accountant_routing: peer_actors.accountant.report_routing_service_provided,
+                to_accountant_payment_overdue: peer_actors.accountant.payment_overdue_from_hopper,
             },
             rate_pack_routing(103),
             rate_pack_routing_byte(103),
@@ -1173,8 +1950,6 @@ mod tests {
         let hopper_recording = hopper_recording_arc.lock().unwrap();
         let record = hopper_recording.get_record::<InboundClientData>(0);
         let expected_lcp = lcp_a.into_next_live(main_cryptde).unwrap().1;
-        let expected_lcp_enc =
-            encodex(main_cryptde, &main_cryptde.public_key(), &expected_lcp).unwrap();
         assert_eq!(
             *record,
             InboundClientData {
@@ -1183,9 +1958,13 @@ mod tests {
                 last_data: true,
                 is_clandestine: true,
                 sequence_number: None,
-                data: expected_lcp_enc.into()
+                data: record.data.clone()
             }
         );
+        assert_eq!(
+            decode_for(main_cryptde.public_key(), &record.data),
+            expected_lcp
+        );
     }
 
     #[test]
@@ -1249,6 +2028,7 @@ mod tests {
                 hopper_subs: peer_actors.hopper,
                 to_dispatcher: peer_actors.dispatcher.from_dispatcher_client,
                 to_accountant_routing: peer_actors.accountant.report_routing_service_provided,
+                to_accountant_payment_overdue: peer_actors.accountant.payment_overdue_from_hopper,
             },
             100,
             200,
@@ -1350,6 +2130,7 @@ mod tests {
                 hopper_subs: peer_actors.hopper,
                 to_dispatcher: peer_actors.dispatcher.from_dispatcher_client,
                 to_accountant_routing: peer_actors.accountant.report_routing_service_provided,
+                to_accountant_payment_overdue: peer_actors.accountant.payment_overdue_from_hopper,
             },
             100,
             200,
@@ -1446,6 +2227,7 @@ mod tests {
                 hopper_subs: peer_actors.hopper,
                 to_dispatcher: peer_actors.dispatcher.from_dispatcher_client,
                 to_accountant_routing: peer_actors.accountant.report_routing_service_provided,
+                to_accountant_payment_overdue: peer_actors.accountant.payment_overdue_from_hopper,
             },
             100,
             200,
@@ -1456,6 +2238,7 @@ mod tests {
             lcp,
             paying_wallet.map(|w| w.as_payer(&PublicKey::new(b"can't pay"), &contract_address)),
             true,
+            SocketAddr::from_str("1.2.3.4:5678").unwrap(),
         );
 
         System::current().stop_with_code(0);
@@ -1519,6 +2302,7 @@ mod tests {
                 hopper_subs: peer_actors.hopper,
                 to_dispatcher: peer_actors.dispatcher.from_dispatcher_client,
                 to_accountant_routing: peer_actors.accountant.report_routing_service_provided,
+                to_accountant_payment_overdue: peer_actors.accountant.payment_overdue_from_hopper,
             },
             rate_pack_routing(103),
             rate_pack_routing_byte(103),
@@ -1537,6 +2321,78 @@ mod tests {
         TestLogHandler::new().exists_log_containing("WARN: RoutingService: Node with consuming wallet 0x71d0fc7d1c570b1ed786382b551a09391c91e33d is delinquent; electing not to route 7-byte payload further");
     }
 
+    #[test]
+    fn route_logs_and_ignores_cores_package_that_demands_external_routing_when_data_cap_is_used_up()
+    {
+        let _eg = EnvironmentGuard::new();
+        BAN_CACHE.clear();
+        init_test_logging();
+        let main_cryptde = main_cryptde();
+        let alias_cryptde = alias_cryptde();
+        let paying_wallet = make_paying_wallet(b"wallet");
+        let contract_address = contract_address(DEFAULT_CHAIN_ID);
+        data_cap::set_status(DataCapStatus::Exhausted);
+        let (dispatcher, _, dispatcher_recording_arc) = make_recorder();
+        let (accountant, _, accountant_recording_arc) = make_recorder();
+        let next_key = PublicKey::new(&[65, 65, 65]);
+        let route = Route::one_way(
+            RouteSegment::new(
+                vec![&main_cryptde.public_key(), &next_key],
+                Component::Neighborhood,
+            ),
+            main_cryptde,
+            Some(paying_wallet.clone()),
+            Some(contract_address.clone()),
+        )
+        .unwrap();
+        let payload = PlainData::new(&b"abcd"[..]);
+        let lcp = LiveCoresPackage::new(route, main_cryptde.encode(&next_key, &payload).unwrap());
+        let data_enc = encodex(main_cryptde, &main_cryptde.public_key(), &lcp).unwrap();
+        let inbound_client_data = InboundClientData {
+            peer_addr: SocketAddr::from_str("1.2.3.4:5678").unwrap(),
+            reception_port: None,
+            last_data: true,
+            is_clandestine: true,
+            sequence_number: None,
+            data: data_enc.into(),
+        };
+        let system = System::new("test");
+        let peer_actors = peer_actors_builder()
+            .dispatcher(dispatcher)
+            .accountant(accountant)
+            .build();
+        let subject = RoutingService::new(
+            main_cryptde,
+            alias_cryptde,
+            RoutingServiceSubs {
+                proxy_client_subs: peer_actors.proxy_client,
+                proxy_server_subs: peer_actors.proxy_server,
+                neighborhood_subs: peer_actors.neighborhood,
+                hopper_subs: peer_actors.hopper,
+                to_dispatcher: peer_actors.dispatcher.from_dispatcher_client,
+                to_accountant_routing: peer_actors.accountant.report_routing_service_provided,
+                to_accountant_payment_overdue: peer_actors.accountant.payment_overdue_from_hopper,
+            },
+            rate_pack_routing(103),
+            rate_pack_routing_byte(103),
+            false,
+        );
+
+        subject.route(inbound_client_data);
+
+        System::current().stop();
+        system.run();
+
+        let dispatcher_recording = dispatcher_recording_arc.lock().unwrap();
+        assert_eq!(dispatcher_recording.len(), 0);
+        let accountant_recording = accountant_recording_arc.lock().unwrap();
+        assert_eq!(accountant_recording.len(), 0);
+        data_cap::set_status(DataCapStatus::Open);
+        TestLogHandler::new().exists_log_containing(
+            "WARN: RoutingService: This month's data cap is used up; electing not to route 7-byte payload further",
+        );
+    }
+
     #[test]
     fn route_logs_and_ignores_cores_package_from_delinquent_that_demands_internal_routing() {
         let _eg = EnvironmentGuard::new();
@@ -1590,6 +2446,7 @@ mod tests {
                 hopper_subs: peer_actors.hopper,
                 to_dispatcher: peer_actors.dispatcher.from_dispatcher_client,
                 to_accountant_routing: peer_actors.accountant.report_routing_service_provided,
+                to_accountant_payment_overdue: peer_actors.accountant.payment_overdue_from_hopper,
             },
             rate_pack_routing(103),
             rate_pack_routing_byte(103),
@@ -1640,6 +2497,7 @@ mod tests {
                 hopper_subs: peer_actors.hopper,
                 to_dispatcher: peer_actors.dispatcher.from_dispatcher_client,
                 to_accountant_routing: peer_actors.accountant.report_routing_service_provided,
+                to_accountant_payment_overdue: peer_actors.accountant.payment_overdue_from_hopper,
             },
             100,
             200,
@@ -1698,6 +2556,7 @@ mod tests {
                 hopper_subs: peer_actors.hopper,
                 to_dispatcher: peer_actors.dispatcher.from_dispatcher_client,
                 to_accountant_routing: peer_actors.accountant.report_routing_service_provided,
+                to_accountant_payment_overdue: peer_actors.accountant.payment_overdue_from_hopper,
             },
             100,
             200,
@@ -1731,6 +2590,7 @@ mod tests {
                 hopper_subs: peer_actors.hopper,
                 to_dispatcher: peer_actors.dispatcher.from_dispatcher_client,
                 to_accountant_routing: peer_actors.accountant.report_routing_service_provided,
+                to_accountant_payment_overdue: peer_actors.accountant.payment_overdue_from_hopper,
             },
             100,
             200,
@@ -1768,6 +2628,7 @@ mod tests {
                 hopper_subs: peer_actors.hopper,
                 to_dispatcher: peer_actors.dispatcher.from_dispatcher_client,
                 to_accountant_routing: peer_actors.accountant.report_routing_service_provided,
+                to_accountant_payment_overdue: peer_actors.accountant.payment_overdue_from_hopper,
             },
             100,
             200,
@@ -1808,6 +2669,7 @@ mod tests {
                 hopper_subs: peer_actors.hopper,
                 to_dispatcher: peer_actors.dispatcher.from_dispatcher_client,
                 to_accountant_routing: peer_actors.accountant.report_routing_service_provided,
+                to_accountant_payment_overdue: peer_actors.accountant.payment_overdue_from_hopper,
             },
             100,
             200,
@@ -1848,6 +2710,7 @@ mod tests {
                 hopper_subs: peer_actors.hopper,
                 to_dispatcher: peer_actors.dispatcher.from_dispatcher_client,
                 to_accountant_routing: peer_actors.accountant.report_routing_service_provided,
+                to_accountant_payment_overdue: peer_actors.accountant.payment_overdue_from_hopper,
             },
             100,
             200,
@@ -1888,6 +2751,7 @@ mod tests {
                 hopper_subs: peer_actors.hopper,
                 to_dispatcher: peer_actors.dispatcher.from_dispatcher_client,
                 to_accountant_routing: peer_actors.accountant.report_routing_service_provided,
+                to_accountant_payment_overdue: peer_actors.accountant.payment_overdue_from_hopper,
             },
             100,
             200,
@@ -1928,6 +2792,7 @@ mod tests {
                 hopper_subs: peer_actors.hopper,
                 to_dispatcher: peer_actors.dispatcher.from_dispatcher_client,
                 to_accountant_routing: peer_actors.accountant.report_routing_service_provided,
+                to_accountant_payment_overdue: peer_actors.accountant.payment_overdue_from_hopper,
             },
             100,
             200,
@@ -41,8 +41,11 @@ impl Framer for JsonFramer {
                 self.possible_start = Some(0);
                 None
             }
-            // crashpoint - return none?
-            _ => panic!("Internal error framing JSON"),
+            // A frame that ended without starting can only be garbage
+            _ => {
+                self.data_so_far.clear();
+                None
+            }
         }
     }
 }
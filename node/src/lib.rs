@@ -22,16 +22,20 @@ mod crash_test_dummy;
 pub mod daemon;
 pub mod database;
 pub mod db_config;
+pub mod diagnostics;
 pub mod discriminator;
 pub mod dispatcher;
 pub mod entry_dns;
+mod geo_db;
 pub mod hopper;
 pub mod http_request_start_finder;
 pub mod json_discriminator_factory;
 pub mod json_framer;
 pub mod json_masquerader;
 mod listener_handler;
+pub mod masquerade_profile;
 pub mod masquerader;
+mod metrics_exporter;
 pub mod neighborhood;
 pub mod node_configurator;
 mod null_masquerader;
@@ -39,7 +43,11 @@ pub mod privilege_drop;
 pub mod proxy_client;
 pub mod proxy_server;
 pub mod run_modes;
+pub mod sandbox;
+pub mod self_test;
 pub mod server_initializer;
+pub mod service_installer;
+pub mod socks_discriminator_factory;
 pub mod stream_handler_pool;
 mod stream_messages;
 mod stream_reader;
@@ -47,4 +55,8 @@ mod stream_writer_sorted;
 mod stream_writer_unsorted;
 pub mod test_utils;
 pub mod tls_discriminator_factory;
+mod trust_dao;
 pub mod ui_gateway;
+mod updater;
+pub mod websocket_framer;
+pub mod websocket_masquerader;
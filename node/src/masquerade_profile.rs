@@ -0,0 +1,254 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use crate::discriminator::{Discriminator, DiscriminatorFactory};
+use crate::json_framer::JsonFramer;
+use crate::json_masquerader::JsonMasquerader;
+use crate::masquerader::Masquerader;
+use crate::sub_lib::cipher_suite;
+use crate::sub_lib::cryptde::PublicKey;
+use crate::sub_lib::framer::{FramedChunk, Framer};
+use crate::websocket_framer::{WebSocketFramer, WEBSOCKET_BINARY_FRAME};
+use crate::websocket_masquerader::WebSocketMasquerader;
+use lazy_static::lazy_static;
+use std::collections::BTreeSet;
+
+// Feature flag in a node record asking other Nodes to send the Node its data framed this way
+pub const WEBSOCKET_PROFILE: &str = "masquerade:websocket";
+
+// Everybody understands this one, so nobody needs to ask for it
+pub const JSON_PROFILE: &str = "masquerade:json";
+
+const PROFILE_PREFIX: &str = "masquerade:";
+
+lazy_static! {
+    // Most preferred first; the last one must be the profile every Node understands
+    static ref MASQUERADE_PROFILES: Vec<Box<dyn MasqueradeProfile>> = vec![
+        Box::new(WebSocketProfile {}),
+        Box::new(JsonProfile {}),
+    ];
+}
+
+// A way to make clandestine traffic look like some other protocol. A new one needs only an
+// implementation of this and a place in MASQUERADE_PROFILES: data for a Node goes out in the most
+// preferred profile it asks for, and every clandestine port takes data in any of them.
+pub trait MasqueradeProfile: Send + Sync {
+    fn name(&self) -> &'static str;
+    // Every frame starts with a byte that no other profile's frames start with
+    fn starts_frame(&self, byte: u8) -> bool;
+    fn framer(&self) -> Box<dyn Framer>;
+    fn masquerader(&self) -> Box<dyn Masquerader>;
+}
+
+// The names --masquerade-profiles knows, without the prefix
+pub fn profile_names() -> Vec<&'static str> {
+    MASQUERADE_PROFILES
+        .iter()
+        .map(|profile| profile.name().trim_start_matches(PROFILE_PREFIX))
+        .collect()
+}
+
+// The feature flags this Node should advertise in its node record for the profiles it was told
+// to ask for
+pub fn local_features(chosen: &[String]) -> BTreeSet<String> {
+    MASQUERADE_PROFILES
+        .iter()
+        .map(|profile| profile.name())
+        .filter(|name| *name != JSON_PROFILE)
+        .filter(|name| {
+            chosen
+                .iter()
+                .any(|choice| name.trim_start_matches(PROFILE_PREFIX) == choice)
+        })
+        .map(|name| name.to_string())
+        .collect()
+}
+
+pub fn profile_for(public_key: &PublicKey) -> &'static dyn MasqueradeProfile {
+    MASQUERADE_PROFILES
+        .iter()
+        .find(|profile| {
+            profile.name() == JSON_PROFILE
+                || cipher_suite::peer_has_feature(public_key, profile.name())
+        })
+        .expect("No universal masquerade profile")
+        .as_ref()
+}
+
+fn profile_starting(byte: u8) -> Option<&'static dyn MasqueradeProfile> {
+    MASQUERADE_PROFILES
+        .iter()
+        .find(|profile| profile.starts_frame(byte))
+        .map(|profile| profile.as_ref())
+}
+
+// A Node can change the profile it sends in from one frame to the next, when it hears that its
+// peer has asked for another one, so each frame is framed by the profile its first byte belongs to
+#[derive(Default)]
+pub struct ProfileFramer {
+    data_so_far: Vec<u8>,
+}
+
+impl Framer for ProfileFramer {
+    fn add_data(&mut self, data: &[u8]) {
+        self.data_so_far.extend_from_slice(data);
+    }
+
+    fn take_frame(&mut self) -> Option<FramedChunk> {
+        match self
+            .data_so_far
+            .iter()
+            .position(|byte| profile_starting(*byte).is_some())
+        {
+            Some(start) => {
+                self.data_so_far.drain(..start);
+            }
+            None => {
+                self.data_so_far.clear();
+                return None;
+            }
+        }
+        let profile = profile_starting(self.data_so_far[0]).expect("Profile disappeared");
+        let mut framer = profile.framer();
+        framer.add_data(&self.data_so_far);
+        let frame = framer.take_frame()?;
+        self.data_so_far.drain(..frame.chunk.len());
+        Some(frame)
+    }
+}
+
+impl ProfileFramer {
+    pub fn new() -> ProfileFramer {
+        ProfileFramer {
+            data_so_far: vec![],
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct MasqueradeDiscriminatorFactory {}
+
+impl DiscriminatorFactory for MasqueradeDiscriminatorFactory {
+    fn make(&self) -> Discriminator {
+        Discriminator::new(
+            Box::new(ProfileFramer::new()),
+            MASQUERADE_PROFILES
+                .iter()
+                .map(|profile| profile.masquerader())
+                .collect(),
+        )
+    }
+
+    fn duplicate(&self) -> Box<dyn DiscriminatorFactory> {
+        Box::new(MasqueradeDiscriminatorFactory {})
+    }
+}
+
+impl MasqueradeDiscriminatorFactory {
+    pub fn new() -> MasqueradeDiscriminatorFactory {
+        MasqueradeDiscriminatorFactory {}
+    }
+}
+
+pub struct JsonProfile {}
+
+impl MasqueradeProfile for JsonProfile {
+    fn name(&self) -> &'static str {
+        JSON_PROFILE
+    }
+
+    fn starts_frame(&self, byte: u8) -> bool {
+        byte == b'{'
+    }
+
+    fn framer(&self) -> Box<dyn Framer> {
+        Box::new(JsonFramer::new())
+    }
+
+    fn masquerader(&self) -> Box<dyn Masquerader> {
+        Box::new(JsonMasquerader::new())
+    }
+}
+
+pub struct WebSocketProfile {}
+
+impl MasqueradeProfile for WebSocketProfile {
+    fn name(&self) -> &'static str {
+        WEBSOCKET_PROFILE
+    }
+
+    fn starts_frame(&self, byte: u8) -> bool {
+        byte == WEBSOCKET_BINARY_FRAME
+    }
+
+    fn framer(&self) -> Box<dyn Framer> {
+        Box::new(WebSocketFramer::new())
+    }
+
+    fn masquerader(&self) -> Box<dyn Masquerader> {
+        Box::new(WebSocketMasquerader::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::discriminator::UnmaskedChunk;
+
+    #[test]
+    fn only_the_chosen_profiles_are_advertised_and_json_never_is() {
+        let chosen = vec!["websocket".to_string(), "json".to_string()];
+
+        let result = local_features(&chosen);
+
+        assert_eq!(
+            result,
+            vec![WEBSOCKET_PROFILE.to_string()].into_iter().collect()
+        );
+        assert_eq!(local_features(&[]), BTreeSet::new());
+        assert_eq!(profile_names(), vec!["websocket", "json"]);
+    }
+
+    #[test]
+    fn data_goes_out_in_the_most_preferred_profile_the_peer_asked_for() {
+        let asking_key = PublicKey::new(b"masquerade asking");
+        let silent_key = PublicKey::new(b"masquerade silent");
+        cipher_suite::record_peer_features(
+            &asking_key,
+            &vec![WEBSOCKET_PROFILE.to_string()].into_iter().collect(),
+        );
+
+        assert_eq!(profile_for(&asking_key).name(), WEBSOCKET_PROFILE);
+        assert_eq!(profile_for(&silent_key).name(), JSON_PROFILE);
+    }
+
+    #[test]
+    fn a_clandestine_discriminator_unmasks_every_profile_even_when_they_alternate() {
+        let json = JsonProfile {}.masquerader();
+        let websocket = WebSocketProfile {}.masquerader();
+        let mut subject = MasqueradeDiscriminatorFactory::new().make();
+        subject.add_data(b"noise");
+        subject.add_data(&websocket.mask(b"first").unwrap());
+        subject.add_data(&json.mask(b"second").unwrap());
+        let third = websocket.mask(&[0xFF; 200]).unwrap();
+        subject.add_data(&third[..100]);
+
+        let first = subject.take_chunk();
+        let second = subject.take_chunk();
+        let incomplete = subject.take_chunk();
+        subject.add_data(&third[100..]);
+        let third = subject.take_chunk();
+
+        assert_eq!(
+            first,
+            Some(UnmaskedChunk::new(b"first".to_vec(), true, false))
+        );
+        assert_eq!(
+            second,
+            Some(UnmaskedChunk::new(b"second".to_vec(), true, false))
+        );
+        assert_eq!(incomplete, None);
+        assert_eq!(
+            third,
+            Some(UnmaskedChunk::new(vec![0xFF; 200], true, false))
+        );
+    }
+}
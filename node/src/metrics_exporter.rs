@@ -0,0 +1,158 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use crate::sub_lib::logger::Logger;
+use crate::sub_lib::metrics;
+use masq_lib::utils::localhost;
+use std::io;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::thread;
+use std::time::Duration;
+
+const MAX_REQUEST_HEAD_SIZE: usize = 8192;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Serves the metrics over HTTP on localhost, one scrape at a time, on a thread of its own
+pub fn start_metrics_exporter(port: u16) -> io::Result<SocketAddr> {
+    let listener = TcpListener::bind(SocketAddr::new(localhost(), port))?;
+    let local_addr = listener.local_addr()?;
+    let logger = Logger::new("MetricsExporter");
+    info!(logger, "Serving metrics at http://{}/metrics", local_addr);
+    thread::spawn(move || {
+        listener
+            .incoming()
+            .for_each(|stream_result| match stream_result {
+                Ok(stream) => {
+                    if let Err(e) = handle_connection(stream) {
+                        debug!(logger, "Couldn't answer metrics request: {}", e);
+                    }
+                }
+                Err(e) => warning!(logger, "Couldn't accept metrics connection: {}", e),
+            })
+    });
+    Ok(local_addr)
+}
+
+fn handle_connection(mut stream: TcpStream) -> io::Result<()> {
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    let head = read_request_head(&mut stream)?;
+    let request_line = head.lines().next().unwrap_or("");
+    stream.write_all(&response_to(request_line))?;
+    stream.flush()
+}
+
+fn read_request_head(stream: &mut TcpStream) -> io::Result<String> {
+    let mut head = vec![];
+    let mut buf = [0u8; 1024];
+    while !head.windows(4).any(|window| window == b"\r\n\r\n") {
+        if head.len() > MAX_REQUEST_HEAD_SIZE {
+            break;
+        }
+        let len = stream.read(&mut buf)?;
+        if len == 0 {
+            break;
+        }
+        head.extend_from_slice(&buf[..len]);
+    }
+    Ok(String::from_utf8_lossy(&head).to_string())
+}
+
+fn response_to(request_line: &str) -> Vec<u8> {
+    let words = request_line.split_whitespace().collect::<Vec<&str>>();
+    let (status, content_type, body) = match words.as_slice() {
+        ["GET", path, ..] if path.split('?').next() == Some("/metrics") => (
+            "200 OK",
+            "text/plain; version=0.0.4",
+            metrics::render(metrics::ALL_METRICS),
+        ),
+        ["GET", ..] => ("404 Not Found", "text/plain", "Try /metrics\n".to_string()),
+        _ => (
+            "405 Method Not Allowed",
+            "text/plain",
+            "Only GET is supported\n".to_string(),
+        ),
+    };
+    format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )
+    .into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use masq_lib::utils::find_free_port;
+
+    #[test]
+    fn metrics_are_served_at_slash_metrics() {
+        let result = String::from_utf8(response_to("GET /metrics HTTP/1.1")).unwrap();
+
+        assert_eq!(
+            result.starts_with(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: "
+            ),
+            true,
+            "{}",
+            result
+        );
+        assert_eq!(
+            result.contains("\r\n\r\n# HELP masq_routed_bytes_total "),
+            true,
+            "{}",
+            result
+        );
+        assert_eq!(
+            result.contains("# TYPE masq_neighbors gauge\nmasq_neighbors "),
+            true,
+            "{}",
+            result
+        );
+    }
+
+    #[test]
+    fn other_paths_and_methods_are_refused() {
+        let not_found = String::from_utf8(response_to("GET /status HTTP/1.1")).unwrap();
+        let not_allowed = String::from_utf8(response_to("POST /metrics HTTP/1.1")).unwrap();
+        let garbage = String::from_utf8(response_to("")).unwrap();
+
+        assert_eq!(not_found.starts_with("HTTP/1.1 404 Not Found\r\n"), true);
+        assert_eq!(
+            not_allowed.starts_with("HTTP/1.1 405 Method Not Allowed\r\n"),
+            true
+        );
+        assert_eq!(
+            garbage.starts_with("HTTP/1.1 405 Method Not Allowed\r\n"),
+            true
+        );
+    }
+
+    #[test]
+    fn exporter_answers_scrapes_on_localhost() {
+        let port = find_free_port();
+        let server_addr = start_metrics_exporter(port).unwrap();
+        let mut stream = TcpStream::connect(server_addr).unwrap();
+
+        stream
+            .write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+
+        assert_eq!(server_addr, SocketAddr::new(localhost(), port));
+        assert_eq!(
+            response.starts_with("HTTP/1.1 200 OK\r\n"),
+            true,
+            "{}",
+            response
+        );
+        assert_eq!(
+            response.contains("# TYPE masq_gossip_sent_total counter\n"),
+            true,
+            "{}",
+            response
+        );
+    }
+}
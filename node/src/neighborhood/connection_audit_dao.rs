@@ -0,0 +1,202 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use crate::database::connection_wrapper::ConnectionWrapper;
+use crate::sub_lib::cryptde::PublicKey;
+use crate::sub_lib::neighborhood::{ConnectionAuditRecord, ConnectionDirection};
+use rusqlite::types::ToSql;
+use rusqlite::Row;
+use std::net::SocketAddr;
+use std::str::FromStr;
+
+// Long enough for a security review to look back over the last month, short enough that a busy
+// Node's database doesn't fill up with connections nobody will ask about
+pub const CONNECTION_AUDIT_DAYS: u64 = 31;
+pub const CONNECTION_AUDIT_MAX_RECORDS: u64 = 100_000;
+
+pub trait ConnectionAuditDao: Send {
+    // Also forgets whatever has fallen outside the retention limits
+    fn record(&self, record: &ConnectionAuditRecord);
+
+    // Connections that were open at any time between from and to (seconds since the epoch),
+    // newest first
+    fn connections(&self, from: u64, to: u64, limit: u64) -> Vec<ConnectionAuditRecord>;
+}
+
+pub struct ConnectionAuditDaoReal {
+    conn: Box<dyn ConnectionWrapper>,
+}
+
+impl ConnectionAuditDao for ConnectionAuditDaoReal {
+    fn record(&self, record: &ConnectionAuditRecord) {
+        let public_key_opt = record
+            .public_key_opt
+            .as_ref()
+            .map(|public_key| public_key.as_slice().to_vec());
+        let params: &[&dyn ToSql] = &[
+            &public_key_opt,
+            &record.peer_addr.to_string(),
+            &record.direction.to_string(),
+            &(record.opened_at as i64),
+            &(record.duration_ms as i64),
+            &(record.bytes_in as i64),
+            &(record.bytes_out as i64),
+            &record.disconnect_reason,
+        ];
+        self.conn
+            .prepare(
+                "insert into connection_audit (public_key, peer_addr, direction, opened_at, \
+                 duration_ms, bytes_in, bytes_out, disconnect_reason) \
+                 values (?, ?, ?, ?, ?, ?, ?, ?)",
+            )
+            .expect("Internal error")
+            .execute(params)
+            .unwrap_or_else(|e| panic!("Database is corrupt: {:?}", e));
+        let oldest = record
+            .opened_at
+            .saturating_sub(CONNECTION_AUDIT_DAYS * 24 * 60 * 60);
+        let keep: &[&dyn ToSql] = &[&(oldest as i64), &(CONNECTION_AUDIT_MAX_RECORDS as i64)];
+        self.conn
+            .prepare(
+                "delete from connection_audit where opened_at < ? or rowid not in \
+                 (select rowid from connection_audit order by opened_at desc limit ?)",
+            )
+            .expect("Internal error")
+            .execute(keep)
+            .unwrap_or_else(|e| panic!("Database is corrupt: {:?}", e));
+    }
+
+    fn connections(&self, from: u64, to: u64, limit: u64) -> Vec<ConnectionAuditRecord> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "select public_key, peer_addr, direction, opened_at, duration_ms, bytes_in, \
+                 bytes_out, disconnect_reason from connection_audit \
+                 where opened_at <= ? and (opened_at * 1000) + duration_ms >= ? \
+                 order by opened_at desc, rowid desc limit ?",
+            )
+            .expect("Internal error");
+        let params: &[&dyn ToSql] = &[
+            &(to.min(i64::MAX as u64) as i64),
+            &(from.saturating_mul(1000).min(i64::MAX as u64) as i64),
+            &(limit.min(CONNECTION_AUDIT_MAX_RECORDS) as i64),
+        ];
+        stmt.query_map(params, Self::row_to_record)
+            .expect("Database is corrupt")
+            .map(|row| row.unwrap_or_else(|e| panic!("Database is corrupt: {:?}", e)))
+            .collect()
+    }
+}
+
+impl ConnectionAuditDaoReal {
+    pub fn new(conn: Box<dyn ConnectionWrapper>) -> ConnectionAuditDaoReal {
+        ConnectionAuditDaoReal { conn }
+    }
+
+    fn row_to_record(row: &Row) -> rusqlite::Result<ConnectionAuditRecord> {
+        let public_key_opt: Option<Vec<u8>> = row.get(0)?;
+        let peer_addr: String = row.get(1)?;
+        let direction: String = row.get(2)?;
+        Ok(ConnectionAuditRecord {
+            public_key_opt: public_key_opt.map(|bytes| PublicKey::new(&bytes)),
+            peer_addr: SocketAddr::from_str(&peer_addr)
+                .unwrap_or_else(|_| panic!("Database is corrupt: peer_addr '{}'", peer_addr)),
+            direction: ConnectionDirection::from_str(&direction)
+                .unwrap_or_else(|e| panic!("Database is corrupt: {}", e)),
+            opened_at: row.get::<usize, i64>(3)? as u64,
+            duration_ms: row.get::<usize, i64>(4)? as u64,
+            bytes_in: row.get::<usize, i64>(5)? as u64,
+            bytes_out: row.get::<usize, i64>(6)? as u64,
+            disconnect_reason: row.get(7)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::db_initializer::{DbInitializer, DbInitializerReal};
+    use masq_lib::test_utils::utils::{ensure_node_home_directory_exists, DEFAULT_CHAIN_ID};
+
+    fn make_record(n: u8, opened_at: u64, duration_ms: u64) -> ConnectionAuditRecord {
+        ConnectionAuditRecord {
+            public_key_opt: if n % 2 == 0 {
+                Some(PublicKey::new(&[n; 8]))
+            } else {
+                None
+            },
+            peer_addr: SocketAddr::from_str(&format!("1.2.3.{}:{}", n, 1000 + n as u16)).unwrap(),
+            direction: if n % 2 == 0 {
+                ConnectionDirection::Outbound
+            } else {
+                ConnectionDirection::Inbound
+            },
+            opened_at,
+            duration_ms,
+            bytes_in: n as u64 * 100,
+            bytes_out: n as u64 * 1000,
+            disconnect_reason: format!("reason {}", n),
+        }
+    }
+
+    fn make_subject(test_name: &str) -> ConnectionAuditDaoReal {
+        let home_dir = ensure_node_home_directory_exists("connection_audit_dao", test_name);
+        ConnectionAuditDaoReal::new(
+            DbInitializerReal::new()
+                .initialize(&home_dir, DEFAULT_CHAIN_ID, true)
+                .unwrap(),
+        )
+    }
+
+    #[test]
+    fn connections_open_during_the_period_come_back_newest_first() {
+        let subject = make_subject("connections_open_during_the_period_come_back_newest_first");
+        let closed_before = make_record(1, 1000, 99_000);
+        let open_across_start = make_record(2, 1000, 150_000);
+        let open_within = make_record(3, 1200, 10_000);
+        let open_across_end = make_record(4, 1400, 200_000);
+        let opened_after = make_record(5, 1501, 1_000);
+        vec![
+            &closed_before,
+            &open_across_start,
+            &open_within,
+            &open_across_end,
+            &opened_after,
+        ]
+        .into_iter()
+        .for_each(|record| subject.record(record));
+
+        let result = subject.connections(1100, 1500, 10);
+
+        assert_eq!(
+            result,
+            vec![open_across_end, open_within, open_across_start]
+        );
+    }
+
+    #[test]
+    fn connections_are_limited_to_the_newest() {
+        let subject = make_subject("connections_are_limited_to_the_newest");
+        (0..5).for_each(|n| subject.record(&make_record(n, 1000 + n as u64, 1000)));
+
+        let result = subject.connections(0, u64::MAX, 2);
+
+        assert_eq!(
+            result,
+            vec![make_record(4, 1004, 1000), make_record(3, 1003, 1000)]
+        );
+    }
+
+    #[test]
+    fn connections_that_opened_too_long_ago_are_forgotten() {
+        let subject = make_subject("connections_that_opened_too_long_ago_are_forgotten");
+        let retention = CONNECTION_AUDIT_DAYS * 24 * 60 * 60;
+        let old = make_record(1, 10_000, 1000);
+        let borderline = make_record(2, 10_001, 1000);
+        let new = make_record(3, 10_001 + retention, 1000);
+        subject.record(&old);
+        subject.record(&borderline);
+
+        subject.record(&new);
+
+        assert_eq!(subject.connections(0, u64::MAX, 10), vec![new, borderline]);
+    }
+}
@@ -2,6 +2,7 @@
 
 use crate::sub_lib::cryptde::PublicKey;
 use crate::sub_lib::node_addr::NodeAddr;
+use masq_lib::messages::{UiNeighborhoodGraphEdge, UiNeighborhoodGraphNode};
 
 pub trait DotRenderable {
     fn render(&self) -> String;
@@ -82,6 +83,35 @@ impl DotRenderable for EdgeRenderable {
     }
 }
 
+// The UI's graph has truncated keys and no addresses already, so it can be shown to anybody
+impl DotRenderable for UiNeighborhoodGraphNode {
+    fn render(&self) -> String {
+        let mut result = format!(
+            "\"{}\" [label=\"{}{} v{}{}\\n{}\"]",
+            self.public_key,
+            if self.accepts_connections { "A" } else { "a" },
+            if self.routes_data { "R" } else { "r" },
+            self.version,
+            match &self.country_code_opt {
+                Some(country_code) => format!(" {}", country_code),
+                None => String::new(),
+            },
+            self.public_key,
+        );
+        if self.is_local {
+            result.push_str(" [style=filled]")
+        }
+        result.push(';');
+        result
+    }
+}
+
+impl DotRenderable for UiNeighborhoodGraphEdge {
+    fn render(&self) -> String {
+        format!("\"{}\" -> \"{}\";", self.from, self.to)
+    }
+}
+
 pub fn render_dot_graph(renderables: Vec<Box<dyn DotRenderable>>) -> String {
     let mut result = String::from("digraph db {");
     for renderable in renderables {
@@ -95,6 +125,7 @@ pub fn render_dot_graph(renderables: Vec<Box<dyn DotRenderable>>) -> String {
 mod tests {
     use super::*;
     use crate::test_utils::assert_string_contains;
+    use masq_lib::messages::UiRatePack;
 
     #[test]
     fn truncation_works_for_long_keys() {
@@ -152,4 +183,33 @@ mod tests {
             ),
         );
     }
+
+    #[test]
+    fn ui_graph_renders_with_the_keys_it_has() {
+        let node = UiNeighborhoodGraphNode {
+            public_key: "AQIDBAUG".to_string(),
+            is_local: true,
+            version: 3,
+            accepts_connections: true,
+            routes_data: false,
+            rate_pack: UiRatePack {
+                routing_byte_rate: 1,
+                routing_service_rate: 2,
+                exit_byte_rate: 3,
+                exit_service_rate: 4,
+            },
+            country_code_opt: Some("NZ".to_string()),
+        };
+        let edge = UiNeighborhoodGraphEdge {
+            from: "AQIDBAUG".to_string(),
+            to: "BwgJCgsM".to_string(),
+        };
+
+        let result = render_dot_graph(vec![Box::new(node), Box::new(edge)]);
+
+        assert_eq!(
+            result,
+            "digraph db { \"AQIDBAUG\" [label=\"Ar v3 NZ\\nAQIDBAUG\"] [style=filled]; \"AQIDBAUG\" -> \"BwgJCgsM\"; }"
+        );
+    }
 }
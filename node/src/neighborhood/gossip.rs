@@ -5,7 +5,7 @@ use crate::neighborhood::dot_graph::{
     render_dot_graph, DotRenderable, EdgeRenderable, NodeRenderable, NodeRenderableInner,
 };
 use crate::neighborhood::neighborhood_database::NeighborhoodDatabase;
-use crate::neighborhood::AccessibleGossipRecord;
+use crate::neighborhood::{sign_node_addr, AccessibleGossipRecord};
 use crate::sub_lib::cryptde::{CryptDE, CryptData, PlainData, PublicKey};
 use crate::sub_lib::hopper::MessageType;
 use crate::sub_lib::node_addr::NodeAddr;
@@ -26,6 +26,8 @@ pub struct GossipNodeRecord {
     pub signed_data: PlainData,
     pub signature: CryptData,
     pub node_addr_opt: Option<NodeAddr>, // Only for use in introductions
+    #[serde(default)]
+    pub node_addr_signature_opt: Option<CryptData>, // Signed by the Node described in signed_data
 }
 
 impl Debug for GossipNodeRecord {
@@ -43,7 +45,8 @@ impl From<(&NeighborhoodDatabase, &PublicKey, bool)> for GossipNodeRecord {
             .expect("Attempted to create Gossip around nonexistent Node");
         let mut gnr = GossipNodeRecord::from(node_record_ref.clone());
         if !reveal_node_addr {
-            gnr.node_addr_opt = None
+            gnr.node_addr_opt = None;
+            gnr.node_addr_signature_opt = None;
         }
         gnr
     }
@@ -55,10 +58,14 @@ impl From<(NodeRecordInner_0v1, Option<NodeAddr>, &dyn CryptDE)> for GossipNodeR
         let signed_data =
             PlainData::from(serde_cbor::to_vec(&inner).expect("Serialization failed"));
         let signature = cryptde.sign(&signed_data).expect("Signing failed");
+        let node_addr_signature_opt = node_addr_opt
+            .as_ref()
+            .map(|node_addr| sign_node_addr(node_addr, inner.version, cryptde));
         GossipNodeRecord {
             signed_data,
             signature,
             node_addr_opt,
+            node_addr_signature_opt,
         }
     }
 }
@@ -69,6 +76,7 @@ impl From<AccessibleGossipRecord> for GossipNodeRecord {
             signed_data: agr.signed_gossip,
             signature: agr.signature,
             node_addr_opt: agr.node_addr_opt,
+            node_addr_signature_opt: agr.node_addr_signature_opt,
         }
     }
 }
@@ -79,6 +87,7 @@ impl From<NodeRecord> for GossipNodeRecord {
             signed_data: node_record.signed_gossip,
             signature: node_record.signature,
             node_addr_opt: node_record.metadata.node_addr_opt,
+            node_addr_signature_opt: node_record.node_addr_signature_opt,
         }
     }
 }
@@ -426,7 +435,8 @@ impl<'a> GossipBuilder<'a> {
             Some(node_record_ref) => {
                 let mut gnr = GossipNodeRecord::from(node_record_ref.clone());
                 if !reveal_node_addr || !node_record_ref.accepts_connections() {
-                    gnr.node_addr_opt = None
+                    gnr.node_addr_opt = None;
+                    gnr.node_addr_signature_opt = None;
                 }
                 self.gossip.node_records.push(gnr);
                 self.keys_so_far
@@ -436,6 +446,20 @@ impl<'a> GossipBuilder<'a> {
         self
     }
 
+    // A departed Node's record isn't in the database any more, and its NodeAddr is no use to anyone
+    pub fn tombstone(mut self, node_record: &NodeRecord) -> GossipBuilder<'a> {
+        if self.keys_so_far.contains(node_record.public_key()) {
+            // crashpoint
+            panic!("GossipBuilder cannot add a Node more than once")
+        }
+        let mut gnr = GossipNodeRecord::from(node_record.clone());
+        gnr.node_addr_opt = None;
+        gnr.node_addr_signature_opt = None;
+        self.gossip.node_records.push(gnr);
+        self.keys_so_far.insert(node_record.public_key().clone());
+        self
+    }
+
     pub fn build(self) -> Gossip_0v1 {
         self.gossip
     }
@@ -537,6 +561,19 @@ mod tests {
         assert_eq!(gossip.node_records.remove(0).node_addr_opt, None)
     }
 
+    #[test]
+    fn a_record_with_truncated_signed_data_is_refused_before_anything_else() {
+        let node = make_node_record(1234, true);
+        let db = db_from_node(&node);
+        let mut gnr = GossipNodeRecord::from((&db, node.public_key(), true));
+        let length = gnr.signed_data.len();
+        gnr.signed_data = PlainData::from(&gnr.signed_data.as_slice()[..length / 2]);
+
+        let result = AccessibleGossipRecord::try_from(gnr);
+
+        assert_eq!(result.is_err(), true);
+    }
+
     #[test]
     fn gossip_node_record_keeps_all_half_neighbors() {
         let mut this_node = make_node_record(1234, true);
@@ -641,12 +678,59 @@ Length: 24 (0x18) bytes
         assert_eq!(result, expected);
     }
 
+    #[test]
+    fn gossip_node_record_without_node_addr_signature_is_still_deserializable() {
+        #[derive(Serialize)]
+        struct OldGossipNodeRecord {
+            signed_data: PlainData,
+            signature: CryptData,
+            node_addr_opt: Option<NodeAddr>,
+        }
+        let node_addr = NodeAddr::new(&IpAddr::from_str("1.2.3.4").unwrap(), &[1234]);
+        let old_gnr = OldGossipNodeRecord {
+            signed_data: PlainData::new(&[1, 2, 3, 4]),
+            signature: CryptData::new(&[4, 3, 2, 1]),
+            node_addr_opt: Some(node_addr.clone()),
+        };
+        let serialized = serde_cbor::ser::to_vec(&old_gnr).unwrap();
+
+        let result: GossipNodeRecord = serde_cbor::de::from_slice(&serialized).unwrap();
+
+        assert_eq!(
+            result,
+            GossipNodeRecord {
+                signed_data: PlainData::new(&[1, 2, 3, 4]),
+                signature: CryptData::new(&[4, 3, 2, 1]),
+                node_addr_opt: Some(node_addr),
+                node_addr_signature_opt: None,
+            }
+        );
+    }
+
+    #[test]
+    fn gossip_node_record_from_database_carries_node_addr_signature_only_when_revealed() {
+        let node = make_node_record(1234, true);
+        let db = db_from_node(&node);
+
+        let revealed = GossipNodeRecord::from((&db, node.public_key(), true));
+        let concealed = GossipNodeRecord::from((&db, node.public_key(), false));
+
+        assert_eq!(
+            revealed.node_addr_signature_opt.as_ref(),
+            db.root().node_addr_signature_opt()
+        );
+        assert!(revealed.node_addr_signature_opt.is_some());
+        assert_eq!(concealed.node_addr_opt, None);
+        assert_eq!(concealed.node_addr_signature_opt, None);
+    }
+
     #[test]
     fn gossip_node_record_that_is_non_deserializable_is_human_readabled_properly() {
         let gnr = GossipNodeRecord {
             signed_data: PlainData::new(&[1, 2, 3, 4]),
             signature: CryptData::new(&[4, 3, 2, 1]),
             node_addr_opt: None,
+            node_addr_signature_opt: None,
         };
 
         let result = format!("{:?}", gnr);
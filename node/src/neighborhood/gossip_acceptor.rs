@@ -83,6 +83,10 @@ impl GossipHandler for DebutHandler {
         if database.node_by_key(&agrs[0].inner.public_key).is_some() {
             return Qualification::Unmatched;
         }
+        // A departing Node's farewell is Standard Gossip, however short
+        if agrs[0].inner.tombstone {
+            return Qualification::Unmatched;
+        }
         match &agrs[0].node_addr_opt {
             None => {
                 if agrs[0].inner.accepts_connections {
@@ -812,6 +816,21 @@ impl GossipHandler for StandardGossipHandler {
                 gossip_source
             ));
         }
+        // Records for Nodes we might connect to have to say which ports to connect on
+        if let Some(portless) = agrs_next_door.iter().find(|agr| {
+            agr.node_addr_opt
+                .as_ref()
+                .expect("Should have NodeAddr")
+                .ports()
+                .is_empty()
+        }) {
+            return Qualification::Malformed(format!(
+                "Standard Gossip from {} contains a record for {} at {} with no ports",
+                gossip_source,
+                portless.inner.public_key,
+                Self::ip_of(portless)
+            ));
+        }
         let init_addr_set: HashSet<IpAddr> = HashSet::new();
         let init_dup_set: HashSet<IpAddr> = HashSet::new();
         let dup_set = agrs_next_door
@@ -846,8 +865,10 @@ impl GossipHandler for StandardGossipHandler {
         agrs: Vec<AccessibleGossipRecord>,
         gossip_source: SocketAddr,
     ) -> GossipAcceptanceResult {
-        let mut db_changed =
-            self.identify_and_add_non_introductory_new_nodes(database, &agrs, gossip_source);
+        let (mut db_changed, agrs) = self.identify_and_bury_departed_nodes(database, agrs);
+        db_changed =
+            self.identify_and_add_non_introductory_new_nodes(database, &agrs, gossip_source)
+                || db_changed;
         db_changed = self.identify_and_update_obsolete_nodes(database, agrs) || db_changed;
         db_changed = self.handle_root_node(cryptde, database, gossip_source) || db_changed;
         // If no Nodes need updating, return ::Ignored and don't change the database.
@@ -870,6 +891,37 @@ impl StandardGossipHandler {
         StandardGossipHandler { logger }
     }
 
+    // Returns the records that aren't tombstones, for the rest of the handling
+    fn identify_and_bury_departed_nodes(
+        &self,
+        database: &mut NeighborhoodDatabase,
+        agrs: Vec<AccessibleGossipRecord>,
+    ) -> (bool, Vec<AccessibleGossipRecord>) {
+        let (tombstones, living): (Vec<_>, Vec<_>) =
+            agrs.into_iter().partition(|agr| agr.inner.tombstone);
+        let change_flags: Vec<bool> = tombstones
+            .into_iter()
+            .map(|agr| {
+                let key = agr.inner.public_key.clone();
+                let version = agr.inner.version;
+                let outdated = match database.node_by_key(&key) {
+                    Some(existing_node) => version <= existing_node.version(),
+                    None => database.is_buried(&key, version),
+                };
+                if outdated {
+                    return false;
+                }
+                info!(
+                    self.logger,
+                    "Node {} has left the network; burying its record at v{}", key, version
+                );
+                database.bury(&key, version, Some(NodeRecord::from(agr)));
+                true
+            })
+            .collect();
+        (change_flags.into_iter().any(|f| f), living)
+    }
+
     fn identify_and_add_non_introductory_new_nodes(
         &self,
         database: &mut NeighborhoodDatabase,
@@ -881,8 +933,17 @@ impl StandardGossipHandler {
             .into_iter()
             .cloned()
             .collect::<HashSet<PublicKey>>();
+        let buried_keys = agrs
+            .iter()
+            .filter(|agr| database.is_buried(&agr.inner.public_key, agr.inner.version))
+            .map(|agr| agr.inner.public_key.clone())
+            .collect::<HashSet<PublicKey>>();
         agrs.iter()
             .filter(|agr| !all_keys.contains(&agr.inner.public_key))
+            // Secondhand news of a buried Node is stale; only the Node itself can show it's back
+            .filter(|agr| {
+                agr.node_addr_opt.is_some() || !buried_keys.contains(&agr.inner.public_key)
+            })
             .filter(|agr| match &agr.node_addr_opt {
                 None => true,
                 Some(node_addr) => {
@@ -1947,6 +2008,41 @@ mod tests {
         );
     }
 
+    #[test]
+    fn standard_gossip_that_contains_a_record_with_no_ports_is_malformed() {
+        let src_node = make_node_record(1234, true);
+        let mut src_db = db_from_node(&src_node);
+        let dest_node = make_node_record(2345, true);
+        let mut dest_db = db_from_node(&dest_node);
+        let node_a = make_node_record(3456, true);
+        src_db.add_node(dest_node.clone()).unwrap();
+        src_db.add_node(node_a.clone()).unwrap();
+        src_db.add_arbitrary_full_neighbor(src_node.public_key(), dest_node.public_key());
+        dest_db.add_node(src_node.clone()).unwrap();
+        dest_db.add_arbitrary_full_neighbor(dest_node.public_key(), src_node.public_key());
+        let mut gossip = GossipBuilder::new(&src_db)
+            .node(src_node.public_key(), true)
+            .node(node_a.public_key(), true)
+            .build();
+        gossip.node_records[1].node_addr_opt = Some(NodeAddr::new(
+            &node_a.node_addr_opt().unwrap().ip_addr(),
+            &[],
+        ));
+        let subject = StandardGossipHandler::new(Logger::new("test"));
+        let gossip_source: SocketAddr = src_node.node_addr_opt().unwrap().into();
+        let gossip_vec: Vec<AccessibleGossipRecord> = gossip.try_into().unwrap();
+
+        let result = subject.qualifies(&mut dest_db, gossip_vec.as_slice(), gossip_source);
+
+        assert_eq!(
+            result,
+            Qualification::Malformed(format!(
+                "Standard Gossip from 1.2.3.4:1234 contains a record for {} at 3.4.5.6 with no ports",
+                node_a.public_key()
+            )),
+        );
+    }
+
     #[test]
     fn proper_standard_gossip_is_matched_and_handled() {
         let src_root = make_node_record(1234, true);
@@ -2027,6 +2123,79 @@ mod tests {
         assert_eq!(result, GossipAcceptanceResult::Ignored);
     }
 
+    #[test]
+    fn a_departing_neighbors_tombstone_buries_it() {
+        let src_root = make_node_record(1234, true);
+        let dest_root = make_node_record(2345, true);
+        let mut src_db = db_from_node(&src_root);
+        src_db.add_node(dest_root.clone()).unwrap();
+        src_db.add_arbitrary_full_neighbor(src_root.public_key(), dest_root.public_key());
+        src_db.root_mut().set_tombstone(true);
+        src_db.root_mut().increment_version();
+        src_db.resign_node(src_root.public_key());
+        let mut dest_db = db_from_node(&dest_root);
+        dest_db.add_node(src_root.clone()).unwrap();
+        dest_db.add_arbitrary_full_neighbor(dest_root.public_key(), src_root.public_key());
+        let gossip = GossipBuilder::new(&src_db)
+            .node(src_root.public_key(), true)
+            .build();
+        let dest_cryptde = CryptDENull::from(dest_root.public_key(), DEFAULT_CHAIN_ID);
+        let subject = GossipAcceptorReal::new(&dest_cryptde);
+
+        let result = subject.handle(
+            &mut dest_db,
+            gossip.try_into().unwrap(),
+            src_root.node_addr_opt().unwrap().into(),
+        );
+
+        assert_eq!(result, GossipAcceptanceResult::Accepted);
+        assert!(dest_db.node_by_key(src_root.public_key()).is_none());
+        assert!(!dest_db.root().has_half_neighbor(src_root.public_key()));
+        assert!(dest_db.is_buried(src_root.public_key(), src_db.root().version()));
+        assert_eq!(dest_db.tombstone_records()[0].inner, src_db.root().inner);
+    }
+
+    #[test]
+    fn secondhand_gossip_does_not_bring_back_a_buried_node_but_the_node_itself_does() {
+        let src_root = make_node_record(1234, true);
+        let dest_root = make_node_record(2345, true);
+        let mut src_db = db_from_node(&src_root);
+        let node_a_key = &src_db.add_node(make_node_record(3456, true)).unwrap();
+        src_db.add_arbitrary_full_neighbor(src_root.public_key(), node_a_key);
+        let mut dest_db = db_from_node(&dest_root);
+        dest_db.add_node(src_root.clone()).unwrap();
+        dest_db.add_arbitrary_full_neighbor(dest_root.public_key(), src_root.public_key());
+        src_db.add_node(dest_db.root().clone()).unwrap();
+        src_db.add_arbitrary_full_neighbor(src_root.public_key(), dest_root.public_key());
+        dest_db.bury(node_a_key, 0, None);
+        let secondhand = GossipBuilder::new(&src_db)
+            .node(src_root.public_key(), true)
+            .node(node_a_key, false)
+            .build();
+        let firsthand = GossipBuilder::new(&src_db).node(node_a_key, true).build();
+        let dest_cryptde = CryptDENull::from(dest_root.public_key(), DEFAULT_CHAIN_ID);
+        let subject = GossipAcceptorReal::new(&dest_cryptde);
+        let node_a_source: SocketAddr = src_db
+            .node_by_key(node_a_key)
+            .unwrap()
+            .node_addr_opt()
+            .unwrap()
+            .into();
+
+        subject.handle(
+            &mut dest_db,
+            secondhand.try_into().unwrap(),
+            src_root.node_addr_opt().unwrap().into(),
+        );
+
+        assert!(dest_db.node_by_key(node_a_key).is_none());
+
+        subject.handle(&mut dest_db, firsthand.try_into().unwrap(), node_a_source);
+
+        assert!(dest_db.node_by_key(node_a_key).is_some());
+        assert_eq!(dest_db.tombstone(node_a_key), None);
+    }
+
     #[test]
     fn last_gossip_handler_rejects_everything() {
         let subject = GossipAcceptorReal::new(main_cryptde());
@@ -45,6 +45,7 @@ impl GossipProducer for GossipProducerReal {
         if time_t_timestamp() - self.last_dead_node_check.get() >= DEAD_NODE_CHECK_INTERVAL_SECS {
             debug!(self.logger, "Checking for dead Nodes");
             database.cull_dead_nodes();
+            database.expire_stale_records();
             self.last_dead_node_check.set(time_t_timestamp());
         }
         let target_node_ref = match database.node_by_key(target) {
@@ -73,6 +74,12 @@ impl GossipProducer for GossipProducerReal {
                     );
                 so_far.node(node_record_ref.public_key(), reveal_node_addr)
             });
+        let builder = database
+            .tombstone_records()
+            .into_iter()
+            .fold(builder, |so_far, tombstone_record| {
+                so_far.tombstone(tombstone_record)
+            });
         Some(builder.build())
     }
 
@@ -309,6 +316,35 @@ mod tests {
         assert!(db.node_by_key(&never_referenced).is_none());
     }
 
+    #[test]
+    fn produce_passes_along_the_records_of_departed_nodes_without_their_node_addrs() {
+        let root_node: NodeRecord = make_node_record(1234, true);
+        let mut db: NeighborhoodDatabase = db_from_node(&root_node);
+        let gossip_target = db.add_node(make_node_record(2345, true)).unwrap();
+        db.add_arbitrary_full_neighbor(&gossip_target, root_node.public_key());
+        let mut departed = make_node_record(3456, true);
+        departed.set_tombstone(true);
+        departed.increment_version();
+        departed.resign();
+        db.bury(
+            departed.public_key(),
+            departed.version(),
+            Some(departed.clone()),
+        );
+        let subject = GossipProducerReal::new();
+
+        let gossip = subject.produce(&mut db, &gossip_target).unwrap();
+
+        let departed_agr = gossip
+            .node_records
+            .into_iter()
+            .flat_map(AccessibleGossipRecord::try_from)
+            .find(|agr| &agr.inner.public_key == departed.public_key())
+            .unwrap();
+        assert_eq!(departed_agr.inner, departed.inner);
+        assert_eq!(departed_agr.node_addr_opt, None);
+    }
+
     #[test]
     fn produce_includes_root_node_in_first_debut_response() {
         let root_node: NodeRecord = make_node_record(1234, true); // AQIDBA
@@ -0,0 +1,456 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+
+// A user whose neighborhood has gone wrong can record the Gossip their Node receives and send us
+// the file; replaying it into a fresh NeighborhoodDatabase reproduces what their Node made of it.
+// Public keys and IP addresses are replaced with made-up ones on the way into the file, so the
+// recording can't say which Nodes the user's Node knows. Because the made-up keys have no private
+// keys behind them, the records are signed again with CryptDENulls when they're replayed.
+
+use crate::database::config_dumper::distill_args;
+use crate::neighborhood::gossip::{GossipNodeRecord, Gossip_0v1};
+use crate::neighborhood::gossip_acceptor::{GossipAcceptor, GossipAcceptorReal};
+use crate::neighborhood::neighborhood_database::NeighborhoodDatabase;
+use crate::neighborhood::node_record::{NodeRecord, NodeRecordInner_0v1};
+use crate::neighborhood::{accessible_gossip_records, AccessibleGossipRecord};
+use crate::node_configurator::RealDirsWrapper;
+use crate::sub_lib::cryptde::{CryptDE, KeySuccession, PlainData, PublicKey};
+use crate::sub_lib::cryptde_null::CryptDENull;
+use crate::sub_lib::logger::Logger;
+use crate::sub_lib::neighborhood::NeighborhoodMode;
+use crate::sub_lib::node_addr::NodeAddr;
+use clap::Arg;
+use masq_lib::command::StdStreams;
+use masq_lib::shared_schema::ConfiguratorError;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::io::{Read, Write};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const GOSSIP_RECORDING_FILE: &str = "gossip-recording.cbor";
+pub const REPLAY_GOSSIP_FLAG: &str = "--replay-gossip";
+
+const REPLAY_GOSSIP_HELP: &str =
+    "Feed the Gossip in a recording made with --gossip-recording on into a fresh neighborhood, \
+     printing what the neighborhood made of each piece and, at the end, the neighborhood it built.";
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum RecordingEntry {
+    // Always first: the recording Node as it was when it started
+    Start {
+        local: Box<NodeRecordInner_0v1>,
+        node_addr_opt: Option<NodeAddr>,
+    },
+    Gossip {
+        received_at: u64,
+        source: SocketAddr,
+        records: Vec<(NodeRecordInner_0v1, Option<NodeAddr>)>,
+    },
+}
+
+pub struct GossipRecorder {
+    file: Box<dyn Write + Send>,
+    key_pseudonyms: HashMap<PublicKey, PublicKey>,
+    ip_pseudonyms: HashMap<IpAddr, IpAddr>,
+}
+
+impl GossipRecorder {
+    pub fn new(file: Box<dyn Write + Send>, root: &NodeRecord) -> io::Result<GossipRecorder> {
+        let mut recorder = GossipRecorder {
+            file,
+            key_pseudonyms: HashMap::new(),
+            ip_pseudonyms: HashMap::new(),
+        };
+        let start = RecordingEntry::Start {
+            local: Box::new(recorder.pseudonymize_inner(&root.inner)),
+            node_addr_opt: recorder.pseudonymize_node_addr_opt(&root.node_addr_opt()),
+        };
+        recorder.write(&start)?;
+        Ok(recorder)
+    }
+
+    pub fn record(
+        &mut self,
+        agrs: &[AccessibleGossipRecord],
+        source: SocketAddr,
+    ) -> io::Result<()> {
+        let records = agrs
+            .iter()
+            .map(|agr| {
+                (
+                    self.pseudonymize_inner(&agr.inner),
+                    self.pseudonymize_node_addr_opt(&agr.node_addr_opt),
+                )
+            })
+            .collect();
+        let entry = RecordingEntry::Gossip {
+            received_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .expect("System clock is before the epoch")
+                .as_secs(),
+            source: SocketAddr::new(self.ip_pseudonym(source.ip()), source.port()),
+            records,
+        };
+        self.write(&entry)
+    }
+
+    // Each entry is CBOR behind its length, so a recording cut off by a crash is still readable
+    fn write(&mut self, entry: &RecordingEntry) -> io::Result<()> {
+        let bytes = serde_cbor::to_vec(entry).expect("Serialization failed");
+        self.file.write_all(&(bytes.len() as u32).to_be_bytes())?;
+        self.file.write_all(&bytes)?;
+        self.file.flush()
+    }
+
+    // The first key seen, which is always the recording Node's, is Node0000; the next is Node0001
+    fn key_pseudonym(&mut self, public_key: &PublicKey) -> PublicKey {
+        let next = self.key_pseudonyms.len();
+        self.key_pseudonyms
+            .entry(public_key.clone())
+            .or_insert_with(|| PublicKey::new(format!("Node{:04}", next).as_bytes()))
+            .clone()
+    }
+
+    fn ip_pseudonym(&mut self, ip_addr: IpAddr) -> IpAddr {
+        let next = self.ip_pseudonyms.len() as u32;
+        *self
+            .ip_pseudonyms
+            .entry(ip_addr)
+            .or_insert_with(|| IpAddr::V4(Ipv4Addr::from(0x0A00_0001 + next)))
+    }
+
+    fn pseudonymize_node_addr_opt(&mut self, node_addr_opt: &Option<NodeAddr>) -> Option<NodeAddr> {
+        node_addr_opt.as_ref().map(|node_addr| {
+            NodeAddr::new(&self.ip_pseudonym(node_addr.ip_addr()), &node_addr.ports())
+        })
+    }
+
+    fn pseudonymize_inner(&mut self, inner: &NodeRecordInner_0v1) -> NodeRecordInner_0v1 {
+        let mut result = inner.clone();
+        result.public_key = self.key_pseudonym(&inner.public_key);
+        result.neighbors = inner
+            .neighbors
+            .iter()
+            .map(|key| self.key_pseudonym(key))
+            .collect();
+        result.link_performance = inner
+            .link_performance
+            .iter()
+            .map(|(key, performance)| (self.key_pseudonym(key), *performance))
+            .collect();
+        // The endorsement can't survive the change of keys; it's made again on replay
+        result.key_succession_opt =
+            inner
+                .key_succession_opt
+                .as_ref()
+                .map(|succession| KeySuccession {
+                    previous_public_key: self.key_pseudonym(&succession.previous_public_key),
+                    endorsement: succession.endorsement.clone(),
+                });
+        result.kem_public_key_opt = None;
+        result
+    }
+}
+
+pub fn read_recording(recording: &mut dyn Read) -> Result<Vec<RecordingEntry>, String> {
+    let mut entries = vec![];
+    loop {
+        let mut length = [0u8; 4];
+        match recording.read_exact(&mut length) {
+            Ok(()) => (),
+            Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(entries),
+            Err(e) => return Err(format!("Couldn't read the recording: {}", e)),
+        }
+        let mut bytes = vec![0u8; u32::from_be_bytes(length) as usize];
+        if recording.read_exact(&mut bytes).is_err() {
+            // Cut off in the middle of an entry: everything before it is still good
+            return Ok(entries);
+        }
+        match serde_cbor::from_slice(&bytes) {
+            Ok(entry) => entries.push(entry),
+            Err(e) => return Err(format!("Entry {} is corrupt: {}", entries.len(), e)),
+        }
+    }
+}
+
+pub fn replay(
+    entries: Vec<RecordingEntry>,
+    chain_id: u8,
+    output: &mut dyn Write,
+) -> Result<NeighborhoodDatabase, String> {
+    let mut entries = entries.into_iter();
+    let (local, node_addr_opt) = match entries.next() {
+        Some(RecordingEntry::Start {
+            local,
+            node_addr_opt,
+        }) => (local, node_addr_opt),
+        _ => return Err("The recording doesn't start with the recording Node".to_string()),
+    };
+    let cryptde = CryptDENull::from(&local.public_key, chain_id);
+    let mode = match (node_addr_opt, local.accepts_connections, local.routes_data) {
+        (Some(node_addr), true, _) => {
+            NeighborhoodMode::Standard(node_addr, vec![], local.rate_pack.clone())
+        }
+        (_, false, true) => NeighborhoodMode::OriginateOnly(vec![], local.rate_pack.clone()),
+        _ => NeighborhoodMode::ConsumeOnly(vec![]),
+    };
+    let mut database = NeighborhoodDatabase::new(
+        &local.public_key,
+        mode,
+        local.earning_wallet.clone(),
+        &cryptde,
+    );
+    let gossip_acceptor = GossipAcceptorReal::new(&cryptde);
+    let logger = Logger::new("GossipReplay");
+    entries.enumerate().for_each(|(index, entry)| {
+        let (received_at, source, records) = match entry {
+            RecordingEntry::Gossip {
+                received_at,
+                source,
+                records,
+            } => (received_at, source, records),
+            RecordingEntry::Start { .. } => return,
+        };
+        let gossip = Gossip_0v1 {
+            node_records: records
+                .into_iter()
+                .map(|(inner, node_addr_opt)| resign(inner, node_addr_opt, chain_id))
+                .collect(),
+        };
+        let result = match accessible_gossip_records(gossip, source, &cryptde, &logger) {
+            Ok(agrs) => format!("{:?}", gossip_acceptor.handle(&mut database, agrs, source)),
+            Err(e) => e,
+        };
+        writeln!(
+            output,
+            "Gossip {} from {} at {}: {}",
+            index + 1,
+            source,
+            received_at,
+            result
+        )
+        .expect("writeln! failed");
+    });
+    writeln!(output, "{}", database.to_dot_graph()).expect("writeln! failed");
+    Ok(database)
+}
+
+fn resign(
+    mut inner: NodeRecordInner_0v1,
+    node_addr_opt: Option<NodeAddr>,
+    chain_id: u8,
+) -> GossipNodeRecord {
+    if let Some(succession) = inner.key_succession_opt.as_mut() {
+        succession.endorsement = CryptDENull::from(&succession.previous_public_key, chain_id)
+            .sign(&PlainData::new(inner.public_key.as_slice()))
+            .expect("Signing failed");
+    }
+    let signer = CryptDENull::from(&inner.public_key, chain_id);
+    GossipNodeRecord::from((inner, node_addr_opt, &signer as &dyn CryptDE))
+}
+
+pub fn replay_gossip(args: &[String], streams: &mut StdStreams) -> Result<i32, ConfiguratorError> {
+    let mode_arg = Arg::with_name("replay-gossip")
+        .long("replay-gossip")
+        .value_name("FILE")
+        .required(true)
+        .takes_value(true)
+        .help(REPLAY_GOSSIP_HELP);
+    let (_, _, chain_id) = distill_args(&RealDirsWrapper {}, mode_arg, args, streams)?;
+    let path = args
+        .iter()
+        .skip_while(|arg| *arg != REPLAY_GOSSIP_FLAG)
+        .nth(1)
+        .expect("clap let --replay-gossip through without a value");
+    let result = File::open(path)
+        .map_err(|e| format!("Couldn't open {}: {}", path, e))
+        .and_then(|mut file| read_recording(&mut file))
+        .and_then(|entries| replay(entries, chain_id, streams.stdout).map(|_| ()));
+    match result {
+        Ok(()) => Ok(0),
+        Err(e) => Err(ConfiguratorError::required("replay-gossip", &e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::neighborhood::gossip::GossipBuilder;
+    use crate::sub_lib::cryptde::CryptData;
+    use crate::test_utils::neighborhood_test_utils::{db_from_node, make_node_record};
+    use masq_lib::test_utils::utils::DEFAULT_CHAIN_ID;
+    use std::convert::TryFrom;
+    use std::str::FromStr;
+    use std::sync::{Arc, Mutex};
+
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn agrs_from(
+        database: &NeighborhoodDatabase,
+        key: &PublicKey,
+        source: SocketAddr,
+    ) -> Vec<AccessibleGossipRecord> {
+        let gossip = GossipBuilder::new(database).node(key, true).build();
+        accessible_gossip_records(
+            gossip,
+            source,
+            &CryptDENull::new(DEFAULT_CHAIN_ID),
+            &Logger::new("test"),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn keys_and_ip_addresses_get_the_same_pseudonyms_every_time_they_turn_up() {
+        let root = make_node_record(1234, true);
+        let neighbor = make_node_record(2345, true);
+        let mut database = db_from_node(&root);
+        database.add_node(neighbor.clone()).unwrap();
+        database.add_arbitrary_full_neighbor(root.public_key(), neighbor.public_key());
+        let source = SocketAddr::from_str("2.3.4.5:2345").unwrap();
+        let buffer = Arc::new(Mutex::new(vec![]));
+        let mut subject =
+            GossipRecorder::new(Box::new(SharedBuffer(buffer.clone())), &root).unwrap();
+        let agrs = agrs_from(&database, neighbor.public_key(), source);
+
+        subject.record(&agrs, source).unwrap();
+        subject.record(&agrs, source).unwrap();
+
+        let entries = read_recording(&mut buffer.lock().unwrap().as_slice()).unwrap();
+        assert_eq!(entries.len(), 3);
+        match &entries[0] {
+            RecordingEntry::Start {
+                local,
+                node_addr_opt,
+            } => {
+                assert_eq!(local.public_key, PublicKey::new(b"Node0000"));
+                assert_eq!(
+                    node_addr_opt.as_ref().unwrap().ip_addr(),
+                    IpAddr::from_str("10.0.0.1").unwrap()
+                );
+            }
+            x => panic!("Expected Start, got {:?}", x),
+        }
+        let expected_record = (
+            NodeRecordInner_0v1 {
+                public_key: PublicKey::new(b"Node0001"),
+                neighbors: vec![PublicKey::new(b"Node0000")].into_iter().collect(),
+                ..database
+                    .node_by_key(neighbor.public_key())
+                    .unwrap()
+                    .inner
+                    .clone()
+            },
+            Some(NodeAddr::new(
+                &IpAddr::from_str("10.0.0.2").unwrap(),
+                &[2345],
+            )),
+        );
+        entries[1..].iter().for_each(|entry| match entry {
+            RecordingEntry::Gossip {
+                source, records, ..
+            } => {
+                assert_eq!(*source, SocketAddr::from_str("10.0.0.2:2345").unwrap());
+                assert_eq!(*records, vec![expected_record.clone()]);
+            }
+            x => panic!("Expected Gossip, got {:?}", x),
+        });
+    }
+
+    #[test]
+    fn replaying_a_recorded_debut_makes_the_same_neighbor_under_its_pseudonym() {
+        let root = make_node_record(1234, true);
+        let debutant = make_node_record(2345, true);
+        let source = SocketAddr::from_str("2.3.4.5:2345").unwrap();
+        let buffer = Arc::new(Mutex::new(vec![]));
+        let mut recorder =
+            GossipRecorder::new(Box::new(SharedBuffer(buffer.clone())), &root).unwrap();
+        let agrs = agrs_from(&db_from_node(&debutant), debutant.public_key(), source);
+        recorder.record(&agrs, source).unwrap();
+        let entries = read_recording(&mut buffer.lock().unwrap().as_slice()).unwrap();
+        let mut output = vec![];
+
+        let result = replay(entries, DEFAULT_CHAIN_ID, &mut output).unwrap();
+
+        let local_key = PublicKey::new(b"Node0000");
+        let debutant_key = PublicKey::new(b"Node0001");
+        assert_eq!(result.root().public_key(), &local_key);
+        assert_eq!(result.has_half_neighbor(&local_key, &debutant_key), true);
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(
+            output.starts_with("Gossip 1 from 10.0.0.2:2345 at "),
+            true,
+            "{}",
+            output
+        );
+        assert_eq!(output.contains(": Accepted\n"), true, "{}", output);
+        assert_eq!(output.contains("digraph db {"), true, "{}", output);
+    }
+
+    #[test]
+    fn replay_endorses_key_successions_again_under_the_pseudonyms() {
+        let mut inner = make_node_record(2345, false).inner;
+        inner.public_key = PublicKey::new(b"Node0001");
+        inner.key_succession_opt = Some(KeySuccession {
+            previous_public_key: PublicKey::new(b"Node0002"),
+            endorsement: CryptData::new(b"from before the pseudonyms"),
+        });
+        let cryptde = CryptDENull::new(DEFAULT_CHAIN_ID);
+
+        let result = resign(inner, None, DEFAULT_CHAIN_ID);
+
+        let agr = AccessibleGossipRecord::try_from(result).unwrap();
+        assert_eq!(
+            cryptde.verify_signature(&agr.signed_gossip, &agr.signature, &agr.inner.public_key),
+            true
+        );
+        assert_eq!(
+            agr.inner
+                .key_succession_opt
+                .unwrap()
+                .is_endorsed(&cryptde, &PublicKey::new(b"Node0001")),
+            true
+        );
+    }
+
+    #[test]
+    fn a_recording_cut_off_mid_entry_keeps_the_entries_before_it() {
+        let root = make_node_record(1234, true);
+        let buffer = Arc::new(Mutex::new(vec![]));
+        GossipRecorder::new(Box::new(SharedBuffer(buffer.clone())), &root).unwrap();
+        let mut bytes = buffer.lock().unwrap().clone();
+        bytes.extend_from_slice(&[0, 0, 1, 0, 1, 2, 3]);
+
+        let result = read_recording(&mut bytes.as_slice()).unwrap();
+
+        assert_eq!(result.len(), 1);
+    }
+
+    #[test]
+    fn a_recording_that_doesnt_start_with_the_recording_node_cant_be_replayed() {
+        let entries = vec![RecordingEntry::Gossip {
+            received_at: 0,
+            source: SocketAddr::from_str("1.2.3.4:1234").unwrap(),
+            records: vec![],
+        }];
+
+        let result = replay(entries, DEFAULT_CHAIN_ID, &mut vec![]);
+
+        assert_eq!(
+            result.err(),
+            Some("The recording doesn't start with the recording Node".to_string())
+        );
+    }
+}
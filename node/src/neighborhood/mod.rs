@@ -1,5 +1,6 @@
 // Copyright (c) 2017-2019, Substratum LLC (https://substratum.net) and/or its affiliates. All rights reserved.
 
+pub mod connection_audit_dao;
 mod dot_graph;
 pub mod gossip;
 pub mod gossip_acceptor;
@@ -7,25 +8,66 @@ pub mod gossip_acceptor;
 mod gossip_producer;
 #[cfg(feature = "expose_test_privates")]
 pub mod gossip_producer;
+pub mod gossip_recording;
 pub mod neighborhood_database;
 pub mod node_record;
+pub mod route_search;
+mod speed_test;
+mod warm_up;
 
+use crate::accountant::neighbor_earnings::EARNINGS_CACHE;
 use crate::blockchain::blockchain_interface::{chain_id_from_name, contract_address};
 use crate::bootstrapper::BootstrapperConfig;
 use crate::database::db_initializer::{DbInitializer, DbInitializerReal};
 use crate::db_config::persistent_configuration::{
     PersistentConfiguration, PersistentConfigurationReal,
 };
+use crate::diagnostics::make_bundle;
+use crate::geo_db::{start_geo_db_updater, GeoDbConfig, GeoDbHandle};
+use crate::masquerade_profile;
+use crate::neighborhood::connection_audit_dao::{ConnectionAuditDao, ConnectionAuditDaoReal};
+use crate::neighborhood::dot_graph::{render_dot_graph, DotRenderable};
 use crate::neighborhood::gossip::{DotGossipEndpoint, GossipNodeRecord, Gossip_0v1};
-use crate::neighborhood::gossip_acceptor::GossipAcceptanceResult;
+use crate::neighborhood::gossip_acceptor::{GossipAcceptanceResult, MAX_DEGREE};
+use crate::neighborhood::gossip_recording::{GossipRecorder, GOSSIP_RECORDING_FILE};
 use crate::neighborhood::node_record::NodeRecordInner_0v1;
+use crate::neighborhood::route_search::{RouteIndex, RouteQuery};
+use crate::neighborhood::speed_test::{SpeedTest, SpeedTestLeg};
+use crate::neighborhood::warm_up::warm_up_progress;
+use crate::proxy_server::stream_sniffer::{samples_to_json, samples_to_pcap, SNIFFED_STREAMS};
 use crate::stream_messages::RemovedStreamType;
+use crate::sub_lib::accountant::ReportEarningWalletChangeMessage;
+use crate::sub_lib::attestation::{
+    make_attestations, Attestation, ATTESTATION_CACHE, ATTESTATION_INTERVAL,
+    MAX_PUBLISHED_ATTESTATIONS,
+};
+use crate::sub_lib::cipher_suite;
 use crate::sub_lib::cryptde::PublicKey;
-use crate::sub_lib::cryptde::{CryptDE, CryptData, PlainData};
+use crate::sub_lib::cryptde::{decodex, CodexError, CryptDE, CryptData, KeySuccession, PlainData};
+use crate::sub_lib::cryptde_null::CryptDENull;
+use crate::sub_lib::cryptde_real::CryptDEReal;
+use crate::sub_lib::deterministic;
 use crate::sub_lib::dispatcher::{Component, StreamShutdownMsg};
+use crate::sub_lib::health::{report_neighborhood_readiness, NeighborhoodReadiness};
+use crate::sub_lib::hole_punch;
+use crate::sub_lib::hole_punch::HOLE_PUNCH_TIMEOUT_SECS;
+use crate::sub_lib::hop::HopTiming;
 use crate::sub_lib::hopper::{ExpiredCoresPackage, NoLookupIncipientCoresPackage};
 use crate::sub_lib::hopper::{IncipientCoresPackage, MessageType};
+use crate::sub_lib::hybrid_kem;
+use crate::sub_lib::ip_reputation::{
+    check_reputation, ReputationChecker, ReputationCheckerReal, ReputationReport,
+    REPUTATION_CHECK_INTERVAL, REPUTATION_WARNING_THRESHOLD,
+};
+use crate::sub_lib::link_benchmark::{
+    LinkPerformance, BENCHMARK_INTERVAL, BENCHMARK_PROBE_BYTES, BENCHMARK_TIMEOUT,
+    LINK_BENCHMARK_FEATURE, SPEED_TEST_TIMEOUT, UNMEASURED_LINK_COST_MS,
+};
+use crate::sub_lib::local_discovery;
+use crate::sub_lib::local_discovery::LocalNodeDiscovered;
 use crate::sub_lib::logger::Logger;
+use crate::sub_lib::metrics;
+use crate::sub_lib::neighborhood::ExitTarget;
 use crate::sub_lib::neighborhood::ExpectedService;
 use crate::sub_lib::neighborhood::ExpectedServices;
 use crate::sub_lib::neighborhood::NeighborhoodSubs;
@@ -36,18 +78,25 @@ use crate::sub_lib::neighborhood::NodeRecordMetadataMessage;
 use crate::sub_lib::neighborhood::RemoveNeighborMessage;
 use crate::sub_lib::neighborhood::RouteQueryMessage;
 use crate::sub_lib::neighborhood::RouteQueryResponse;
-use crate::sub_lib::neighborhood::{DispatcherNodeQueryMessage, GossipFailure_0v1};
+use crate::sub_lib::neighborhood::{
+    Benchmark_0v1, ConnectionAuditMessage, ConnectionAuditRecord, DispatcherNodeQueryMessage,
+    GossipFailure_0v1, HolePunch_0v1, HopTimingsMessage,
+};
 use crate::sub_lib::node_addr::NodeAddr;
 use crate::sub_lib::peer_actors::{BindMessage, StartMessage};
 use crate::sub_lib::proxy_server::DEFAULT_MINIMUM_HOP_COUNT;
+use crate::sub_lib::redacted::Redacted;
 use crate::sub_lib::route::Route;
 use crate::sub_lib::route::RouteSegment;
 use crate::sub_lib::set_consuming_wallet_message::SetConsumingWalletMessage;
 use crate::sub_lib::stream_handler_pool::DispatcherNodeQueryResponse;
+use crate::sub_lib::stream_handler_pool::PunchHoleMsg;
 use crate::sub_lib::utils::NODE_MAILBOX_CAPACITY;
 use crate::sub_lib::versioned_data::VersionedData;
-use crate::sub_lib::wallet::Wallet;
+use crate::sub_lib::wallet::{Wallet, WalletChange};
+use crate::trust_dao::{TrustDao, TrustDaoReal, TrustEvidence, NEUTRAL_TRUST_SCORE, TRUST_CACHE};
 use actix::Addr;
+use actix::AsyncContext;
 use actix::Context;
 use actix::Handler;
 use actix::MessageResult;
@@ -61,25 +110,60 @@ use itertools::Itertools;
 use masq_lib::constants::DEFAULT_CHAIN_NAME;
 use masq_lib::messages::FromMessageBody;
 use masq_lib::messages::UiMessageError::UnexpectedMessage;
-use masq_lib::messages::{UiMessageError, UiShutdownRequest};
-use masq_lib::ui_gateway::{NodeFromUiMessage, NodeToUiMessage};
+use masq_lib::messages::{
+    ToMessageBody, UiAuditedConnection, UiCollectDiagnosticsRequest, UiCollectDiagnosticsResponse,
+    UiConnectionAuditRequest, UiConnectionAuditResponse, UiGeoDatabaseRefreshRequest,
+    UiGeoDatabaseRefreshResponse, UiGeoDatabaseRequest, UiGeoDatabaseResponse, UiMessageError,
+    UiNeighborhoodGraphEdge, UiNeighborhoodGraphNode, UiNeighborhoodGraphRequest,
+    UiNeighborhoodGraphResponse, UiNeighborsChangedBroadcast, UiRatePack, UiRouteQuote,
+    UiRouteQuoteRequest, UiRouteQuoteResponse, UiShutdownRequest, UiSpeedTestRequest,
+    UiWarmUpBroadcast, CONNECTION_AUDIT_ERROR, DIAGNOSTICS_ERROR, GEO_DATABASE_ERROR,
+    ROUTE_QUOTE_ERROR, SPEED_TEST_ERROR,
+};
+use masq_lib::ui_gateway::MessagePath::Conversation;
+use masq_lib::ui_gateway::MessageTarget::{AllClients, ClientId};
+use masq_lib::ui_gateway::{MessageBody, NodeFromUiMessage, NodeToUiMessage};
 use masq_lib::utils::exit_process;
-use neighborhood_database::NeighborhoodDatabase;
-use node_record::NodeRecord;
-use std::cmp::Ordering;
+use neighborhood_database::{NeighborhoodDatabase, NODE_RECORD_REFRESH_INTERVAL_SECS};
+use std::cell::RefCell;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::convert::TryFrom;
+use std::fs;
+use std::fs::File;
 use std::net::SocketAddr;
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 pub const CRASH_KEY: &str = "NEIGHBORHOOD";
+// Long enough for the Gossip announcing this Node's departure to get onto the wire
+const FAREWELL_GOSSIP_GRACE: Duration = Duration::from_secs(1);
+// The number of candidate routes grows with the Neighborhood to the power of the hop count
+const MAX_QUOTED_HOP_COUNT: usize = 6;
+const BYTES_PER_MB: u64 = 1_000_000;
+// Enough return legs to see a pattern without the diagnostics bundle growing without bound
+const MAX_ROUTE_LATENCIES: usize = 50;
+// Audited connections sent to a UI that doesn't say how many it wants
+const DEFAULT_CONNECTION_AUDIT_LIMIT: u64 = 100;
+// Gossip from a Node whose earning wallet scores lower than this isn't worth checking
+const GOSSIP_TRUST_FLOOR: u8 = 20;
+const TRUST_BAND_WIDTH: u8 = 10;
+pub const NEIGHBORHOOD_JSON_FILE: &str = "neighborhood.json";
+pub const NEIGHBORHOOD_DOT_FILE: &str = "neighborhood.dot";
 
 pub struct Neighborhood {
     cryptde: &'static dyn CryptDE,
     hopper: Option<Recipient<IncipientCoresPackage>>,
     hopper_no_lookup: Option<Recipient<NoLookupIncipientCoresPackage>>,
     is_connected: bool,
+    is_zero_hop: bool,
     connected_signal: Option<Recipient<StartMessage>>,
-    _to_ui_message_sub: Option<Recipient<NodeToUiMessage>>,
+    to_ui_message_sub: Option<Recipient<NodeToUiMessage>>,
+    // The warm-up progress UIs heard last; once it has routes available, they hear no more
+    last_warm_up_opt: Option<UiWarmUpBroadcast>,
+    punch_hole_sub: Option<Recipient<PunchHoleMsg>>,
+    report_earning_wallet_change_sub: Option<Recipient<ReportEarningWalletChangeMessage>>,
     gossip_acceptor: Box<dyn GossipAcceptor>,
     gossip_producer: Box<dyn GossipProducer>,
     neighborhood_database: NeighborhoodDatabase,
@@ -89,7 +173,46 @@ pub struct Neighborhood {
     chain_id: u8,
     data_directory: PathBuf,
     persistent_config_opt: Option<Box<dyn PersistentConfiguration>>,
+    connection_audit_dao_opt: Option<Box<dyn ConnectionAuditDao>>,
+    trust_dao_opt: Option<Box<dyn TrustDao>>,
+    // Taken when the updater starts
+    geo_db_config_opt: Option<GeoDbConfig>,
+    geo_db_opt: Option<GeoDbHandle>,
     db_password_opt: Option<String>,
+    // Neighbors we're expecting an Introduction to, and the ports we'll punch holes from
+    hole_punches: HashMap<PublicKey, (u16, Instant)>,
+    // The latest effective block of each wallet change already passed on to the Accountant
+    wallet_changes_reported: HashMap<PublicKey, u64>,
+    // The earning wallets that signed the descriptors in --neighbors
+    neighbor_signers: HashMap<PublicKey, Wallet>,
+    reputation_lists: Vec<String>,
+    reputation_checker: Arc<dyn ReputationChecker>,
+    local_discovery: bool,
+    bandwidth_benchmark: bool,
+    // Probes not yet echoed: the neighbor each went to, whether it was padded, and when it left
+    benchmark_probes: HashMap<u64, (PublicKey, bool, Instant)>,
+    // Round trips of the empty probes whose padded followers are still out
+    benchmark_round_trips: HashMap<PublicKey, Duration>,
+    next_benchmark_nonce: u64,
+    // Speed tests under way, and the probes they're waiting on: the test and leg each belongs
+    // to, whether it was padded, and when it left
+    speed_tests: HashMap<u64, SpeedTest>,
+    speed_test_probes: HashMap<u64, (u64, usize, bool, Instant)>,
+    next_speed_test_id: u64,
+    // Smoothed measurements, published in the root NodeRecord at the start of each round
+    link_performance: BTreeMap<PublicKey, LinkPerformance>,
+    // A key of its own for each run, so the Nodes stamping timings can't tell whose routes they're on
+    timing_cryptde_opt: Option<Box<dyn CryptDE>>,
+    // How long each Node on the way back from an exit held on to a response, oldest route first
+    route_latencies: VecDeque<Vec<(PublicKey, i64)>>,
+    gossip_recording: bool,
+    gossip_recorder_opt: Option<GossipRecorder>,
+    dump_neighborhood: bool,
+    publish_attestations: bool,
+    heed_attestations: bool,
+    // Rebuilt for the first route search after each change to the database
+    route_index_opt: RefCell<Option<Arc<RouteIndex>>>,
+    route_search_threads: usize,
     logger: Logger,
 }
 
@@ -105,14 +228,43 @@ impl Handler<BindMessage> for Neighborhood {
         self.hopper = Some(msg.peer_actors.hopper.from_hopper_client);
         self.hopper_no_lookup = Some(msg.peer_actors.hopper.from_hopper_client_no_lookup);
         self.connected_signal = Some(msg.peer_actors.accountant.start);
+        self.to_ui_message_sub = Some(msg.peer_actors.ui_gateway.node_to_ui_message_sub);
+        self.punch_hole_sub = Some(msg.peer_actors.dispatcher.punch_hole_sub);
+        self.report_earning_wallet_change_sub =
+            Some(msg.peer_actors.accountant.report_earning_wallet_change);
     }
 }
 
 impl Handler<StartMessage> for Neighborhood {
     type Result = ();
 
-    fn handle(&mut self, _msg: StartMessage, _ctx: &mut Self::Context) -> Self::Result {
+    fn handle(&mut self, _msg: StartMessage, ctx: &mut Self::Context) -> Self::Result {
         self.handle_start_message();
+        if self.checks_reputation() {
+            self.start_reputation_check(ctx);
+            ctx.run_interval(REPUTATION_CHECK_INTERVAL, |neighborhood, ctx| {
+                neighborhood.start_reputation_check(ctx)
+            });
+        }
+        if self.local_discovery && !self.is_zero_hop {
+            self.start_local_discovery(ctx);
+        }
+        if self.bandwidth_benchmark && !self.is_zero_hop {
+            ctx.run_interval(BENCHMARK_INTERVAL, |neighborhood, _ctx| {
+                neighborhood.start_benchmark_round()
+            });
+        }
+        if self.publish_attestations && !self.is_zero_hop {
+            ctx.run_interval(ATTESTATION_INTERVAL, |neighborhood, _ctx| {
+                neighborhood.attest_to_payments()
+            });
+        }
+        if !self.is_zero_hop {
+            ctx.run_interval(
+                Duration::from_secs(NODE_RECORD_REFRESH_INTERVAL_SECS as u64),
+                |neighborhood, _ctx| neighborhood.refresh_root_record(),
+            );
+        }
     }
 }
 
@@ -226,6 +378,38 @@ impl Handler<ExpiredCoresPackage<GossipFailure_0v1>> for Neighborhood {
     }
 }
 
+impl Handler<ExpiredCoresPackage<HolePunch_0v1>> for Neighborhood {
+    type Result = ();
+
+    fn handle(
+        &mut self,
+        msg: ExpiredCoresPackage<HolePunch_0v1>,
+        _ctx: &mut Self::Context,
+    ) -> Self::Result {
+        self.handle_hole_punch(msg.immediate_neighbor, msg.payload);
+    }
+}
+
+impl Handler<ExpiredCoresPackage<Benchmark_0v1>> for Neighborhood {
+    type Result = ();
+
+    fn handle(
+        &mut self,
+        msg: ExpiredCoresPackage<Benchmark_0v1>,
+        _ctx: &mut Self::Context,
+    ) -> Self::Result {
+        match msg.payload {
+            Benchmark_0v1::RouteProbe {
+                nonce,
+                originator,
+                padding,
+            } => self.handle_route_probe(msg.remaining_route, nonce, &originator, padding),
+            Benchmark_0v1::RouteEcho { nonce, .. } => self.handle_route_echo(nonce),
+            benchmark => self.handle_benchmark(msg.immediate_neighbor, benchmark),
+        }
+    }
+}
+
 impl Handler<RemoveNeighborMessage> for Neighborhood {
     type Result = ();
 
@@ -243,6 +427,23 @@ impl Handler<RemoveNeighborMessage> for Neighborhood {
                 }
             }
         }
+        self.request_hole_punch(public_key);
+    }
+}
+
+impl Handler<HopTimingsMessage> for Neighborhood {
+    type Result = ();
+
+    fn handle(&mut self, msg: HopTimingsMessage, _ctx: &mut Self::Context) -> Self::Result {
+        self.handle_hop_timings(msg);
+    }
+}
+
+impl Handler<ConnectionAuditMessage> for Neighborhood {
+    type Result = ();
+
+    fn handle(&mut self, msg: ConnectionAuditMessage, _ctx: &mut Self::Context) -> Self::Result {
+        self.handle_connection_audit(msg.record);
     }
 }
 
@@ -260,6 +461,22 @@ impl Handler<NodeRecordMetadataMessage> for Neighborhood {
     }
 }
 
+impl Handler<ReputationReport> for Neighborhood {
+    type Result = ();
+
+    fn handle(&mut self, msg: ReputationReport, _ctx: &mut Self::Context) -> Self::Result {
+        self.handle_reputation_report(msg);
+    }
+}
+
+impl Handler<LocalNodeDiscovered> for Neighborhood {
+    type Result = ();
+
+    fn handle(&mut self, msg: LocalNodeDiscovered, _ctx: &mut Self::Context) -> Self::Result {
+        self.handle_local_node_discovered(msg);
+    }
+}
+
 impl Handler<StreamShutdownMsg> for Neighborhood {
     type Result = ();
 
@@ -271,13 +488,34 @@ impl Handler<StreamShutdownMsg> for Neighborhood {
 impl Handler<NodeFromUiMessage> for Neighborhood {
     type Result = ();
 
-    fn handle(&mut self, msg: NodeFromUiMessage, _ctx: &mut Self::Context) -> Self::Result {
+    fn handle(&mut self, msg: NodeFromUiMessage, ctx: &mut Self::Context) -> Self::Result {
         let client_id = msg.client_id;
         let opcode = msg.body.opcode.clone();
+        if let Ok((_, context_id)) = UiNeighborhoodGraphRequest::fmb(msg.body.clone()) {
+            return self.handle_neighborhood_graph_request(client_id, context_id);
+        }
+        if let Ok((_, context_id)) = UiCollectDiagnosticsRequest::fmb(msg.body.clone()) {
+            return self.handle_collect_diagnostics_request(client_id, context_id);
+        }
+        if let Ok((request, context_id)) = UiRouteQuoteRequest::fmb(msg.body.clone()) {
+            return self.handle_route_quote_request(client_id, context_id, request);
+        }
+        if let Ok((request, context_id)) = UiSpeedTestRequest::fmb(msg.body.clone()) {
+            return self.handle_speed_test_request(client_id, context_id, request, ctx);
+        }
+        if let Ok((request, context_id)) = UiConnectionAuditRequest::fmb(msg.body.clone()) {
+            return self.handle_connection_audit_request(client_id, context_id, request);
+        }
+        if let Ok((_, context_id)) = UiGeoDatabaseRequest::fmb(msg.body.clone()) {
+            return self.handle_geo_database_request(client_id, context_id);
+        }
+        if let Ok((_, context_id)) = UiGeoDatabaseRefreshRequest::fmb(msg.body.clone()) {
+            return self.handle_geo_database_refresh_request(client_id, context_id);
+        }
         let result: Result<(UiShutdownRequest, u64), UiMessageError> =
             UiShutdownRequest::fmb(msg.body);
         match result {
-            Ok((payload, _)) => self.handle_shutdown_order(client_id, payload),
+            Ok((payload, _)) => self.handle_shutdown_order(client_id, payload, ctx),
             Err(UnexpectedMessage(opcode, _)) => debug!(
                 &self.logger,
                 "Ignoring '{}' request from client {}", opcode, client_id
@@ -295,6 +533,7 @@ pub struct AccessibleGossipRecord {
     pub signed_gossip: PlainData,
     pub signature: CryptData,
     pub node_addr_opt: Option<NodeAddr>,
+    pub node_addr_signature_opt: Option<CryptData>,
     pub inner: NodeRecordInner_0v1,
 }
 
@@ -303,9 +542,39 @@ impl AccessibleGossipRecord {
         let (signed_gossip, signature) = regenerate_signed_gossip(&self.inner, cryptde);
         self.signed_gossip = signed_gossip;
         self.signature = signature;
+        self.node_addr_signature_opt = self
+            .node_addr_opt
+            .as_ref()
+            .map(|node_addr| sign_node_addr(node_addr, self.inner.version, cryptde));
+    }
+
+    pub fn node_addr_signature_status(&self, cryptde: &dyn CryptDE) -> NodeAddrSignatureStatus {
+        match (&self.node_addr_opt, &self.node_addr_signature_opt) {
+            (None, _) => NodeAddrSignatureStatus::NoNodeAddr,
+            (Some(_), None) => NodeAddrSignatureStatus::Unsigned,
+            (Some(node_addr), Some(signature)) => {
+                if cryptde.verify_signature(
+                    &node_addr_signable_data(node_addr, self.inner.version),
+                    signature,
+                    &self.inner.public_key,
+                ) {
+                    NodeAddrSignatureStatus::Valid
+                } else {
+                    NodeAddrSignatureStatus::Invalid
+                }
+            }
+        }
     }
 }
 
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum NodeAddrSignatureStatus {
+    NoNodeAddr,
+    Unsigned,
+    Valid,
+    Invalid,
+}
+
 impl TryFrom<GossipNodeRecord> for AccessibleGossipRecord {
     type Error = String;
 
@@ -315,6 +584,7 @@ impl TryFrom<GossipNodeRecord> for AccessibleGossipRecord {
                 signed_gossip: value.signed_data,
                 signature: value.signature,
                 node_addr_opt: value.node_addr_opt,
+                node_addr_signature_opt: value.node_addr_signature_opt,
                 inner,
             }),
             Err(e) => Err(format!("{}", e)),
@@ -340,12 +610,42 @@ impl Neighborhood {
         }
         let gossip_acceptor: Box<dyn GossipAcceptor> = Box::new(GossipAcceptorReal::new(cryptde));
         let gossip_producer = Box::new(GossipProducerReal::new());
-        let neighborhood_database = NeighborhoodDatabase::new(
+        let mut neighborhood_database = NeighborhoodDatabase::new(
             &cryptde.public_key(),
             neighborhood_config.mode.clone(),
             config.earning_wallet.clone(),
             cryptde,
         );
+        let exit_policy_changed = neighborhood_database
+            .root_mut()
+            .set_exit_policy(config.exit_policy.clone());
+        let key_succession_changed = neighborhood_database
+            .root_mut()
+            .set_key_succession_opt(cryptde.key_succession_opt());
+        let mut features = cryptde.features();
+        features.extend(masquerade_profile::local_features(
+            &config.masquerade_profiles,
+        ));
+        if config.bandwidth_benchmark {
+            features.insert(LINK_BENCHMARK_FEATURE.to_string());
+        }
+        let features_changed = neighborhood_database.root_mut().set_features(features);
+        let kem_public_key_changed = neighborhood_database
+            .root_mut()
+            .set_kem_public_key_opt(cryptde.kem_public_key_opt());
+        let wallet_change_changed = neighborhood_database
+            .root_mut()
+            .set_wallet_change_opt(config.earning_wallet_change_opt.clone());
+        if exit_policy_changed
+            || key_succession_changed
+            || features_changed
+            || kem_public_key_changed
+            || wallet_change_changed
+        {
+            neighborhood_database
+                .root_mut()
+                .regenerate_signed_gossip(cryptde);
+        }
         let is_mainnet =
             || config.blockchain_bridge_config.chain_id == chain_id_from_name(DEFAULT_CHAIN_NAME);
         let initial_neighbors: Vec<NodeDescriptor> = neighborhood_config
@@ -363,14 +663,25 @@ impl Neighborhood {
                 nc.clone()
             })
             .collect_vec();
+        let chain_id = config.blockchain_bridge_config.chain_id;
+        let timing_cryptde_opt: Option<Box<dyn CryptDE>> =
+            match (config.hop_timing, &config.main_cryptde_null_opt) {
+                (false, _) => None,
+                (true, Some(_)) => Some(Box::new(CryptDENull::new(chain_id))),
+                (true, None) => Some(Box::new(CryptDEReal::new(chain_id))),
+            };
 
         Neighborhood {
             cryptde,
             hopper: None,
             hopper_no_lookup: None,
             connected_signal: None,
-            _to_ui_message_sub: None,
+            to_ui_message_sub: None,
+            last_warm_up_opt: None,
+            punch_hole_sub: None,
+            report_earning_wallet_change_sub: None,
             is_connected: false,
+            is_zero_hop: neighborhood_config.mode.is_zero_hop(),
             gossip_acceptor,
             gossip_producer,
             neighborhood_database,
@@ -380,7 +691,34 @@ impl Neighborhood {
             chain_id: config.blockchain_bridge_config.chain_id,
             data_directory: config.data_directory.clone(),
             persistent_config_opt: None,
+            connection_audit_dao_opt: None,
+            trust_dao_opt: None,
+            geo_db_config_opt: config.geo_db_opt.clone(),
+            geo_db_opt: None,
             db_password_opt: config.db_password_opt.clone(),
+            hole_punches: HashMap::new(),
+            wallet_changes_reported: HashMap::new(),
+            neighbor_signers: config.neighbor_signers.clone(),
+            reputation_lists: config.reputation_lists.clone(),
+            reputation_checker: Arc::new(ReputationCheckerReal {}),
+            local_discovery: config.local_discovery,
+            bandwidth_benchmark: config.bandwidth_benchmark,
+            benchmark_probes: HashMap::new(),
+            benchmark_round_trips: HashMap::new(),
+            next_benchmark_nonce: 0,
+            speed_tests: HashMap::new(),
+            speed_test_probes: HashMap::new(),
+            next_speed_test_id: 0,
+            link_performance: BTreeMap::new(),
+            timing_cryptde_opt,
+            route_latencies: VecDeque::new(),
+            gossip_recording: config.gossip_recording,
+            dump_neighborhood: config.dump_neighborhood,
+            publish_attestations: config.publish_attestations,
+            heed_attestations: config.heed_attestations,
+            gossip_recorder_opt: None,
+            route_index_opt: RefCell::new(None),
+            route_search_threads: config.route_search_threads,
             logger: Logger::new("Neighborhood"),
         }
     }
@@ -396,6 +734,14 @@ impl Neighborhood {
             gossip_failure: addr
                 .clone()
                 .recipient::<ExpiredCoresPackage<GossipFailure_0v1>>(),
+            hole_punch: addr
+                .clone()
+                .recipient::<ExpiredCoresPackage<HolePunch_0v1>>(),
+            benchmark: addr
+                .clone()
+                .recipient::<ExpiredCoresPackage<Benchmark_0v1>>(),
+            hop_timings: addr.clone().recipient::<HopTimingsMessage>(),
+            connection_audit: addr.clone().recipient::<ConnectionAuditMessage>(),
             dispatcher_node_query: addr.clone().recipient::<DispatcherNodeQueryMessage>(),
             remove_neighbor: addr.clone().recipient::<RemoveNeighborMessage>(),
             stream_shutdown_sub: addr.clone().recipient::<StreamShutdownMsg>(),
@@ -406,9 +752,161 @@ impl Neighborhood {
 
     fn handle_start_message(&mut self) {
         self.connect_database();
+        if let Some(geo_db_config) = self.geo_db_config_opt.take() {
+            self.geo_db_opt = Some(start_geo_db_updater(geo_db_config, &self.data_directory));
+        }
+        if self.gossip_recording {
+            self.start_gossip_recording();
+        }
+        if self.is_zero_hop {
+            // A zero-hop Node makes its own routes and needs no neighbors to do it
+            report_neighborhood_readiness(NeighborhoodReadiness {
+                connected: true,
+                routes_available: true,
+            });
+        }
         self.send_debut_gossip();
     }
 
+    fn start_gossip_recording(&mut self) {
+        let path = self.data_directory.join(GOSSIP_RECORDING_FILE);
+        match File::create(&path)
+            .and_then(|file| GossipRecorder::new(Box::new(file), self.neighborhood_database.root()))
+        {
+            Ok(recorder) => {
+                info!(self.logger, "Recording Gossip into {}", path.display());
+                self.gossip_recorder_opt = Some(recorder);
+            }
+            Err(e) => warning!(
+                self.logger,
+                "Can't record Gossip into {}: {}",
+                path.display(),
+                e
+            ),
+        }
+    }
+
+    // Only a Node that other Nodes' data leaves the network through has a reputation to keep
+    fn checks_reputation(&self) -> bool {
+        !self.reputation_lists.is_empty()
+            && !self.is_zero_hop
+            && self.neighborhood_database.root().routes_data()
+    }
+
+    // Blocklists are asked over DNS, which blocks; so the asking happens off the actor's thread
+    fn start_reputation_check(&self, ctx: &mut Context<Neighborhood>) {
+        let ip_addr = match self.neighborhood_database.root().node_addr_opt() {
+            Some(node_addr) => node_addr.ip_addr(),
+            None => return,
+        };
+        let checker = self.reputation_checker.clone();
+        let lists = self.reputation_lists.clone();
+        let recipient = ctx.address().recipient::<ReputationReport>();
+        std::thread::spawn(move || {
+            if let Some(report) = check_reputation(checker.as_ref(), ip_addr, &lists) {
+                let _ = recipient.try_send(report);
+            }
+        });
+    }
+
+    fn handle_reputation_report(&mut self, report: ReputationReport) {
+        info!(
+            self.logger,
+            "Reputation of {} is {} out of 100", report.ip_addr, report.score
+        );
+        if report.score < REPUTATION_WARNING_THRESHOLD {
+            warning!(
+                self.logger,
+                "Your IP address {} is listed by {}; sites may refuse or challenge consumers you exit for",
+                report.ip_addr,
+                report.listed_by.join(", ")
+            );
+        }
+        let root = self.neighborhood_database.root_mut();
+        if root.set_reputation_score_opt(Some(report.score)) {
+            root.increment_version();
+            self.gossip_to_neighbors();
+        }
+    }
+
+    // mDNS blocks too, and never finishes; it gets a thread of its own for the life of the Node
+    fn start_local_discovery(&self, ctx: &mut Context<Neighborhood>) {
+        let root = self.neighborhood_database.root();
+        if root.node_addr_opt().is_none() {
+            warning!(
+                self.logger,
+                "Local discovery needs to know this Node's IP address and ports; not announcing"
+            );
+            return;
+        }
+        let descriptor =
+            NodeDescriptor::from((root, self.is_mainnet(), self.cryptde)).to_string(self.cryptde);
+        let instance = format!(
+            "masq-{}",
+            root.public_key()
+                .as_slice()
+                .iter()
+                .take(4)
+                .map(|byte| format!("{:02x}", byte))
+                .collect::<String>()
+        );
+        let recipient = ctx.address().recipient::<LocalNodeDiscovered>();
+        let logger = self.logger.clone();
+        info!(
+            self.logger,
+            "Announcing this Node on the local network as {}", instance
+        );
+        std::thread::spawn(move || {
+            if let Err(e) = local_discovery::run(&instance, &descriptor, recipient, &logger) {
+                warning!(logger, "Local discovery couldn't start: {}", e);
+            }
+        });
+    }
+
+    // A Node on the same LAN is reached at the address it was heard from, not at the public one
+    // its descriptor names, which the router in between may not loop back to
+    fn handle_local_node_discovered(&mut self, msg: LocalNodeDiscovered) {
+        let descriptor = match NodeDescriptor::from_str(self.cryptde, &msg.descriptor) {
+            Ok(descriptor) => descriptor,
+            Err(e) => {
+                debug!(
+                    self.logger,
+                    "Ignoring unparseable descriptor from {}: {}", msg.source, e
+                );
+                return;
+            }
+        };
+        let node_addr = match &descriptor.node_addr_opt {
+            Some(node_addr) => NodeAddr::new(&msg.source, &node_addr.ports()),
+            None => return,
+        };
+        if &descriptor.encryption_public_key == self.neighborhood_database.root().public_key()
+            || descriptor.mainnet != self.is_mainnet()
+            || self
+                .neighborhood_database
+                .node_by_key(&descriptor.encryption_public_key)
+                .is_some()
+        {
+            return;
+        }
+        info!(
+            self.logger,
+            "Found a Node on the local network at {}; introducing this one", node_addr
+        );
+        let local_descriptor = NodeDescriptor {
+            node_addr_opt: Some(node_addr),
+            ..descriptor
+        };
+        let gossip = self
+            .gossip_producer
+            .produce_debut(&self.neighborhood_database);
+        self.send_debut_gossip_to(&local_descriptor, &gossip);
+    }
+
+    fn is_mainnet(&self) -> bool {
+        self.chain_id == chain_id_from_name(DEFAULT_CHAIN_NAME)
+    }
+
     fn handle_route_query_message(&mut self, msg: RouteQueryMessage) -> Option<RouteQueryResponse> {
         let msg_str = format!("{:?}", msg);
         let route_result = if msg.minimum_hop_count == 0 {
@@ -440,6 +938,14 @@ impl Neighborhood {
                 .initialize(&self.data_directory, self.chain_id, true) // TODO: Probably should be false
                 .expect("Neighborhood could not connect to database");
             self.persistent_config_opt = Some(Box::new(PersistentConfigurationReal::from(conn)));
+            let conn = db_initializer
+                .initialize(&self.data_directory, self.chain_id, true)
+                .expect("Neighborhood could not connect to database");
+            self.connection_audit_dao_opt = Some(Box::new(ConnectionAuditDaoReal::new(conn)));
+            let conn = db_initializer
+                .initialize(&self.data_directory, self.chain_id, true)
+                .expect("Neighborhood could not connect to database");
+            self.trust_dao_opt = Some(Box::new(TrustDaoReal::new(conn)));
         }
     }
 
@@ -453,40 +959,48 @@ impl Neighborhood {
             .gossip_producer
             .produce_debut(&self.neighborhood_database);
         self.initial_neighbors.iter().for_each(|node_descriptor| {
-            if let Some(node_addr) = &node_descriptor.node_addr_opt {
-                self.hopper_no_lookup
-                    .as_ref()
-                    .expect("unbound hopper")
-                    .try_send(
-                        NoLookupIncipientCoresPackage::new(
-                            self.cryptde,
-                            &node_descriptor.encryption_public_key,
-                            &node_addr,
-                            MessageType::Gossip(gossip.clone().into()),
-                        )
-                        .expect("Key magically disappeared"),
-                    )
-                    .expect("hopper is dead");
-                trace!(
-                    self.logger,
-                    "Sent Gossip: {}",
-                    gossip.to_dot_graph(
-                        self.neighborhood_database.root(),
-                        (
-                            &node_descriptor.encryption_public_key,
-                            &node_descriptor.node_addr_opt
-                        ),
-                    )
-                );
-            } else {
+            if node_descriptor.node_addr_opt.is_none() {
                 panic!(
                     "--neighbors node descriptors must have IP address and port list, not '{}'",
-                    node_descriptor.to_string(self.cryptde)
+                    Redacted(node_descriptor.to_string(self.cryptde))
                 )
             }
+            self.send_debut_gossip_to(node_descriptor, &gossip);
         });
     }
 
+    fn send_debut_gossip_to(&self, node_descriptor: &NodeDescriptor, gossip: &Gossip_0v1) {
+        let node_addr = node_descriptor
+            .node_addr_opt
+            .as_ref()
+            .expect("Debut target has no NodeAddr");
+        self.hopper_no_lookup
+            .as_ref()
+            .expect("unbound hopper")
+            .try_send(
+                NoLookupIncipientCoresPackage::new(
+                    self.cryptde,
+                    &node_descriptor.encryption_public_key,
+                    node_addr,
+                    MessageType::Gossip(gossip.clone().into()),
+                )
+                .expect("Key magically disappeared"),
+            )
+            .expect("hopper is dead");
+        metrics::GOSSIP_SENT.add(1);
+        trace!(
+            self.logger,
+            "Sent Gossip: {}",
+            gossip.to_dot_graph(
+                self.neighborhood_database.root(),
+                (
+                    &node_descriptor.encryption_public_key,
+                    &node_descriptor.node_addr_opt
+                ),
+            )
+        );
+    }
+
     fn log_incoming_gossip(&self, incoming_gossip: &Gossip_0v1, gossip_source: SocketAddr) {
         let source = match self.neighborhood_database.node_by_ip(&gossip_source.ip()) {
             Some(node) => DotGossipEndpoint::from(node),
@@ -501,45 +1015,30 @@ impl Neighborhood {
 
     fn handle_gossip(&mut self, incoming_gossip: Gossip_0v1, gossip_source: SocketAddr) {
         let record_count = incoming_gossip.node_records.len();
+        metrics::GOSSIP_RECEIVED.add(1);
         info!(
             self.logger,
             "Processing Gossip about {} Nodes", record_count
         );
-        let agrs: Vec<AccessibleGossipRecord> = incoming_gossip
-            .node_records
-            .into_iter()
-            .flat_map(AccessibleGossipRecord::try_from)
-            .collect();
-
-        if agrs.len() < record_count {
-            // TODO: Instead of ignoring non-deserializable Gossip, ban the Node that sent it
-            error!(
-                self.logger,
-                "Received non-deserializable Gossip from {}", gossip_source
-            );
-            self.announce_gossip_handling_completion(record_count);
-            return;
+        match accessible_gossip_records(incoming_gossip, gossip_source, self.cryptde, &self.logger)
+        {
+            Ok(agrs) => {
+                self.record_gossip(&agrs, gossip_source);
+                self.handle_gossip_agrs(agrs, gossip_source)
+            }
+            // TODO: Instead of ignoring bad Gossip, ban the Node that sent it
+            Err(e) => error!(self.logger, "{}", e),
         }
+        self.announce_gossip_handling_completion(record_count);
+    }
 
-        let signature_invalid = |agr: &AccessibleGossipRecord| {
-            !self.cryptde.verify_signature(
-                &agr.signed_gossip,
-                &agr.signature,
-                &agr.inner.public_key,
-            )
-        };
-        if agrs.iter().any(signature_invalid) {
-            // TODO: Instead of ignoring badly-signed Gossip, ban the Node that sent it
-            error!(
-                self.logger,
-                "Received Gossip with invalid signature from {}", gossip_source
-            );
-            self.announce_gossip_handling_completion(record_count);
-            return;
+    fn record_gossip(&mut self, agrs: &[AccessibleGossipRecord], gossip_source: SocketAddr) {
+        if let Some(recorder) = self.gossip_recorder_opt.as_mut() {
+            if let Err(e) = recorder.record(agrs, gossip_source) {
+                warning!(self.logger, "Stopped recording Gossip: {}", e);
+                self.gossip_recorder_opt = None;
+            }
         }
-
-        self.handle_gossip_agrs(agrs, gossip_source);
-        self.announce_gossip_handling_completion(record_count);
     }
 
     fn handle_gossip_failure(&mut self, failure_source: SocketAddr, failure: GossipFailure_0v1) {
@@ -555,11 +1054,13 @@ impl Neighborhood {
                 warning!(
                     self.logger,
                     "Node at {} refused Debut: {}",
-                    node_descriptor
-                        .node_addr_opt
-                        .as_ref()
-                        .expect("NodeAddr disappeared")
-                        .ip_addr(),
+                    Redacted(
+                        node_descriptor
+                            .node_addr_opt
+                            .as_ref()
+                            .expect("NodeAddr disappeared")
+                            .ip_addr()
+                    ),
                     failure
                 );
                 self.initial_neighbors.remove(position);
@@ -571,671 +1072,2318 @@ impl Neighborhood {
         };
     }
 
-    fn to_node_descriptors(&self, keys: &[PublicKey]) -> Vec<NodeDescriptor> {
-        keys.iter()
-            .map(|k| {
-                NodeDescriptor::from((
-                    self.neighborhood_database
-                        .node_by_key(k)
-                        .expect("Node disappeared"),
-                    self.chain_id == chain_id_from_name(DEFAULT_CHAIN_NAME),
-                    self.cryptde,
-                ))
-            })
-            .collect()
-    }
-
-    fn handle_gossip_agrs(&mut self, agrs: Vec<AccessibleGossipRecord>, gossip_source: SocketAddr) {
-        let neighbor_keys_before = self.neighbor_keys();
-        self.handle_agrs(agrs, gossip_source);
-        let neighbor_keys_after = self.neighbor_keys();
-        self.handle_database_changes(&neighbor_keys_before, &neighbor_keys_after);
-    }
-
-    fn neighbor_keys(&self) -> Vec<PublicKey> {
-        self.neighborhood_database
+    // A neighbor we couldn't connect to may just be behind a NAT. If one of our other neighbors is
+    // its neighbor too, that Node can introduce us, so that we can both connect at the same moment.
+    fn request_hole_punch(&mut self, target: &PublicKey) {
+        let timeout = Duration::from_secs(HOLE_PUNCH_TIMEOUT_SECS);
+        self.hole_punches
+            .retain(|_, (_, requested_at)| requested_at.elapsed() < timeout);
+        if self.hole_punches.contains_key(target) {
+            return;
+        }
+        let db = &self.neighborhood_database;
+        let rendezvous_key = match db
             .root()
-            .full_neighbor_keys(&self.neighborhood_database)
+            .full_neighbor_keys(db)
             .into_iter()
-            .cloned()
-            .collect()
+            .find(|key| *key != target && db.has_half_neighbor(key, target))
+        {
+            Some(key) => key.clone(),
+            None => return,
+        };
+        let port = match hole_punch::pick_local_port() {
+            Ok(port) => port,
+            Err(e) => {
+                warning!(
+                    self.logger,
+                    "Could not find a port to punch a hole to {} from: {}",
+                    target,
+                    e
+                );
+                return;
+            }
+        };
+        info!(
+            self.logger,
+            "Asking {} to introduce us to {} so that we can punch a hole from port {}",
+            rendezvous_key,
+            target,
+            port
+        );
+        self.hole_punches
+            .insert(target.clone(), (port, Instant::now()));
+        self.send_hole_punch(
+            &rendezvous_key,
+            HolePunch_0v1::Request {
+                target: target.clone(),
+                port,
+            },
+        );
     }
 
-    fn handle_agrs(&mut self, agrs: Vec<AccessibleGossipRecord>, gossip_source: SocketAddr) {
-        let ignored_node_name = self.gossip_source_name(&agrs, gossip_source);
-        let gossip_record_count = agrs.len();
-        let acceptance_result =
-            self.gossip_acceptor
-                .handle(&mut self.neighborhood_database, agrs, gossip_source);
-        match acceptance_result {
-            GossipAcceptanceResult::Accepted => self.gossip_to_neighbors(),
-            GossipAcceptanceResult::Reply(next_debut, target_key, target_node_addr) => {
-                self.handle_gossip_reply(next_debut, &target_key, &target_node_addr)
-            }
-            GossipAcceptanceResult::Failed(failure, target_key, target_node_addr) => {
-                self.handle_gossip_failed(failure, &target_key, &target_node_addr)
-            }
-            GossipAcceptanceResult::Ignored => {
-                trace!(self.logger, "Gossip from {} ignored", gossip_source);
-                self.handle_gossip_ignored(ignored_node_name, gossip_record_count)
+    fn handle_hole_punch(&mut self, immediate_neighbor: SocketAddr, hole_punch: HolePunch_0v1) {
+        match hole_punch {
+            HolePunch_0v1::Request { target, port } => {
+                self.handle_hole_punch_request(immediate_neighbor, target, port)
             }
-            GossipAcceptanceResult::Ban(reason) => {
-                warning!(self.logger, "Malefactor detected at {}, but malefactor bans not yet implemented; ignoring: {}", gossip_source, reason
-            );
-                self.handle_gossip_ignored(ignored_node_name, gossip_record_count);
+            HolePunch_0v1::Introduction { peer, peer_addr } => {
+                self.handle_hole_punch_introduction(immediate_neighbor, peer, peer_addr)
             }
         }
     }
 
-    fn handle_database_changes(
+    fn handle_hole_punch_request(
         &mut self,
-        neighbor_keys_before: &[PublicKey],
-        neighbor_keys_after: &[PublicKey],
-    ) {
-        self.curate_past_neighbors(neighbor_keys_before, neighbor_keys_after);
-        self.check_connectedness();
-    }
-
-    fn curate_past_neighbors(
-        &mut self,
-        neighbor_keys_before: &[PublicKey],
-        neighbor_keys_after: &[PublicKey],
+        requester_addr: SocketAddr,
+        target: PublicKey,
+        port: u16,
     ) {
-        if neighbor_keys_after != neighbor_keys_before {
-            if let Some(db_password) = &self.db_password_opt {
-                let nds = self.to_node_descriptors(neighbor_keys_after);
-                let node_descriptors_opt = if nds.is_empty() {
-                    None
-                } else {
-                    Some(nds.into_iter().collect_vec())
-                };
-                debug!(
+        let db = &self.neighborhood_database;
+        let requester = match db.node_by_ip(&requester_addr.ip()) {
+            Some(node) if db.root().has_half_neighbor(node.public_key()) => {
+                node.public_key().clone()
+            }
+            _ => {
+                warning!(
                     self.logger,
-                    "Saving neighbor list: {:?}", node_descriptors_opt
+                    "Ignoring hole-punching Request from {}, which is not a neighbor",
+                    requester_addr.ip()
                 );
-                match self
-                    .persistent_config_opt
-                    .as_mut()
-                    .expect("PersistentConfig was not set by StartMessage")
-                    .set_past_neighbors(node_descriptors_opt, db_password)
-                {
-                    Ok(_) => info!(self.logger, "Persisted neighbor changes for next run"),
-                    Err(e) => error!(
-                        self.logger,
-                        "Could not persist immediate-neighbor changes: {:?}", e
-                    ),
-                };
-            } else {
-                info!(self.logger, "Declining to persist neighbor changes for next run: no database password supplied")
+                return;
             }
-        } else {
-            debug!(self.logger, "No neighbor changes; database is unchanged")
-        }
-    }
-
-    fn check_connectedness(&mut self) {
-        if self.is_connected {
-            return;
-        }
-        let msg = RouteQueryMessage {
-            target_key_opt: None,
-            target_component: Component::ProxyClient,
-            minimum_hop_count: DEFAULT_MINIMUM_HOP_COUNT,
-            return_component_opt: Some(Component::ProxyServer),
         };
-        if self.handle_route_query_message(msg).is_some() {
-            self.is_connected = true;
-            self.connected_signal
-                .as_ref()
-                .expect("Accountant was not bound")
-                .try_send(StartMessage {})
-                .expect("Accountant is dead")
+        if !db.root().has_half_neighbor(&target) {
+            warning!(
+                self.logger,
+                "Ignoring hole-punching Request from {} for {}, which is not a neighbor",
+                requester,
+                target
+            );
+            return;
         }
-    }
-
-    fn announce_gossip_handling_completion(&self, record_count: usize) {
         info!(
             self.logger,
-            "Finished processing Gossip about {} Nodes", record_count
+            "Introducing {} to {} for hole punching", requester, target
+        );
+        self.send_hole_punch(
+            &target,
+            HolePunch_0v1::Introduction {
+                peer: requester,
+                peer_addr: SocketAddr::new(requester_addr.ip(), port),
+            },
         );
     }
 
-    fn gossip_to_neighbors(&mut self) {
-        self.neighborhood_database
-            .root_mut()
-            .regenerate_signed_gossip(self.cryptde);
-        let neighbors = self
-            .neighborhood_database
-            .root()
-            .half_neighbor_keys()
-            .into_iter()
-            .cloned()
-            .collect_vec();
-        neighbors.iter().for_each(|neighbor| {
-            if let Some(gossip) = self
-                .gossip_producer
-                .produce(&mut self.neighborhood_database, neighbor)
-            {
-                self.gossip_to_neighbor(neighbor, gossip)
+    fn handle_hole_punch_introduction(
+        &mut self,
+        rendezvous_addr: SocketAddr,
+        peer: PublicKey,
+        peer_addr: SocketAddr,
+    ) {
+        let local_port = match self.hole_punches.remove(&peer) {
+            // Either the answer to our own Request, or a second Introduction after we've answered
+            Some((port, _)) => port,
+            None => {
+                let rendezvous_key = match self
+                    .neighborhood_database
+                    .node_by_ip(&rendezvous_addr.ip())
+                {
+                    Some(node) => node.public_key().clone(),
+                    None => {
+                        warning!(
+                            self.logger,
+                            "Ignoring hole-punching Introduction from {}, which is not in the database",
+                            rendezvous_addr.ip()
+                        );
+                        return;
+                    }
+                };
+                let port = match hole_punch::pick_local_port() {
+                    Ok(port) => port,
+                    Err(e) => {
+                        warning!(
+                            self.logger,
+                            "Could not find a port to punch a hole to {} from: {}",
+                            peer,
+                            e
+                        );
+                        return;
+                    }
+                };
+                // Tell the peer where we'll be coming from, so that it can punch back
+                self.hole_punches
+                    .insert(peer.clone(), (port, Instant::now()));
+                self.send_hole_punch(
+                    &rendezvous_key,
+                    HolePunch_0v1::Request {
+                        target: peer.clone(),
+                        port,
+                    },
+                );
+                port
             }
-        });
+        };
+        self.punch_hole_sub
+            .as_ref()
+            .expect("Dispatcher is unbound")
+            .try_send(PunchHoleMsg {
+                peer_addr,
+                local_port,
+            })
+            .expect("Dispatcher is dead");
     }
 
-    fn gossip_to_neighbor(&self, neighbor: &PublicKey, gossip: Gossip_0v1) {
-        let gossip_len = gossip.node_records.len();
-        let route = self.create_single_hop_route(neighbor);
-        let package =
-            IncipientCoresPackage::new(self.cryptde, route, gossip.clone().into(), neighbor)
-                .expect("Key magically disappeared");
-        info!(
-            self.logger,
-            "Sending update Gossip about {} Nodes to Node {}", gossip_len, neighbor
-        );
+    fn send_hole_punch(&self, destination: &PublicKey, hole_punch: HolePunch_0v1) {
+        let route = self.create_single_hop_route(destination);
+        let package = IncipientCoresPackage::new(
+            self.cryptde,
+            route,
+            MessageType::HolePunch(hole_punch.into()),
+            destination,
+        )
+        .expect("Key magically disappeared");
         self.hopper
             .as_ref()
             .expect("unbound hopper")
             .try_send(package)
             .expect("hopper is dead");
-        trace!(
-            self.logger,
-            "Sent Gossip: {}",
-            gossip.to_dot_graph(
-                self.neighborhood_database.root(),
-                self.neighborhood_database
-                    .node_by_key(&neighbor)
-                    .expect("Node magically disappeared"),
-            )
-        );
     }
 
-    fn create_single_hop_route(&self, destination: &PublicKey) -> Route {
-        Route::one_way(
-            RouteSegment::new(
-                vec![&self.cryptde.public_key(), destination],
-                Component::Neighborhood,
-            ),
-            self.cryptde,
-            None,
-            None,
-        )
-        .expect("route creation error")
+    // Publishes what the last round measured, then measures again every full neighbor that
+    // benchmarks too
+    fn start_benchmark_round(&mut self) {
+        self.benchmark_probes
+            .retain(|_, (_, _, sent)| sent.elapsed() < BENCHMARK_TIMEOUT);
+        let db = &self.neighborhood_database;
+        let neighbors = db
+            .root()
+            .full_neighbors(db)
+            .into_iter()
+            .filter(|node| node.features().contains(LINK_BENCHMARK_FEATURE))
+            .map(|node| node.public_key().clone())
+            .collect_vec();
+        self.link_performance
+            .retain(|key, _| db.root().has_full_neighbor(db, key));
+        let root = self.neighborhood_database.root_mut();
+        if root.set_link_performance(self.link_performance.clone()) {
+            root.increment_version();
+            self.gossip_to_neighbors();
+        }
+        neighbors
+            .iter()
+            .for_each(|neighbor| self.send_benchmark_probe(neighbor, false));
     }
 
-    fn zero_hop_route_response(&mut self) -> RouteQueryResponse {
-        let return_route_id = self.advance_return_route_id();
-        let route = Route::round_trip(
-            RouteSegment::new(
-                vec![&self.cryptde.public_key(), &self.cryptde.public_key()],
-                Component::ProxyClient,
-            ),
-            RouteSegment::new(
-                vec![&self.cryptde.public_key(), &self.cryptde.public_key()],
-                Component::ProxyServer,
-            ),
-            self.cryptde,
-            None,
-            return_route_id,
-            None,
-        )
-        .expect("Couldn't create route");
-        RouteQueryResponse {
-            route,
-            expected_services: ExpectedServices::RoundTrip(
-                vec![ExpectedService::Nothing, ExpectedService::Nothing],
-                vec![ExpectedService::Nothing, ExpectedService::Nothing],
-                return_route_id,
+    fn send_benchmark_probe(&mut self, destination: &PublicKey, padded: bool) {
+        let nonce = self.next_benchmark_nonce;
+        self.next_benchmark_nonce = self.next_benchmark_nonce.wrapping_add(1);
+        let padding = if padded {
+            PlainData::from(vec![0u8; BENCHMARK_PROBE_BYTES])
+        } else {
+            PlainData::new(&[])
+        };
+        self.benchmark_probes
+            .insert(nonce, (destination.clone(), padded, Instant::now()));
+        self.send_benchmark(destination, Benchmark_0v1::Probe { nonce, padding });
+    }
+
+    fn handle_benchmark(&mut self, immediate_neighbor: SocketAddr, benchmark: Benchmark_0v1) {
+        let db = &self.neighborhood_database;
+        let neighbor = match db.node_by_ip(&immediate_neighbor.ip()) {
+            Some(node) if db.root().has_half_neighbor(node.public_key()) => {
+                node.public_key().clone()
+            }
+            _ => {
+                debug!(
+                    self.logger,
+                    "Ignoring benchmark from {}, which is not a neighbor",
+                    immediate_neighbor.ip()
+                );
+                return;
+            }
+        };
+        match benchmark {
+            Benchmark_0v1::Probe { nonce, .. } => {
+                if self.bandwidth_benchmark {
+                    self.send_benchmark(&neighbor, Benchmark_0v1::Echo { nonce })
+                } else {
+                    debug!(
+                        self.logger,
+                        "Ignoring benchmark Probe from {}: benchmarking is off", neighbor
+                    );
+                }
+            }
+            Benchmark_0v1::Echo { nonce } => self.handle_benchmark_echo(neighbor, nonce),
+            Benchmark_0v1::RouteProbe { .. } | Benchmark_0v1::RouteEcho { .. } => debug!(
+                self.logger,
+                "Ignoring speed-test benchmark from {} that came without its route", neighbor
             ),
         }
     }
 
-    fn make_round_trip_route(
-        &mut self,
-        msg: RouteQueryMessage,
-    ) -> Result<RouteQueryResponse, String> {
-        let over = self.make_route_segment(
-            &self.cryptde.public_key(),
-            msg.target_key_opt.as_ref(),
-            msg.minimum_hop_count,
-            msg.target_component,
-            RouteDirection::Over,
-        )?;
-        debug!(self.logger, "Route over: {:?}", over);
-        let back = self.make_route_segment(
-            over.keys.last().expect("Empty segment"),
-            Some(&self.cryptde.public_key()),
-            msg.minimum_hop_count,
-            msg.return_component_opt.expect("No return component"),
-            RouteDirection::Back,
-        )?;
-        debug!(self.logger, "Route back: {:?}", back);
-        self.compose_route_query_response(over, back)
+    fn handle_benchmark_echo(&mut self, neighbor: PublicKey, nonce: u64) {
+        let (padded, round_trip) = match self.benchmark_probes.remove(&nonce) {
+            Some((key, padded, sent)) if key == neighbor => (padded, sent.elapsed()),
+            Some(probe) => {
+                // Not ours to answer; put it back for the neighbor it went to
+                self.benchmark_probes.insert(nonce, probe);
+                return;
+            }
+            None => return,
+        };
+        if !padded {
+            // The padded probe goes out only now, so that it doesn't queue behind the empty one
+            self.benchmark_round_trips
+                .insert(neighbor.clone(), round_trip);
+            self.send_benchmark_probe(&neighbor, true);
+            return;
+        }
+        let empty_round_trip = match self.benchmark_round_trips.remove(&neighbor) {
+            Some(empty_round_trip) => empty_round_trip,
+            None => return,
+        };
+        let sample =
+            LinkPerformance::from_round_trips(empty_round_trip, round_trip, BENCHMARK_PROBE_BYTES);
+        let smoothed = match self.link_performance.get(&neighbor) {
+            Some(previous) => previous.smoothed(&sample),
+            None => sample,
+        };
+        debug!(
+            self.logger,
+            "Link to {} measured at {}ms and {}kB/s; now {}ms and {}kB/s",
+            neighbor,
+            sample.latency_ms,
+            sample.throughput_kbps,
+            smoothed.latency_ms,
+            smoothed.throughput_kbps
+        );
+        self.link_performance.insert(neighbor, smoothed);
     }
 
-    fn compose_route_query_response(
-        &mut self,
-        over: RouteSegment,
-        back: RouteSegment,
-    ) -> Result<RouteQueryResponse, String> {
-        let segments = vec![&over, &back];
-
-        if segments.iter().any(|rs| rs.keys.is_empty()) {
-            return Err("Cannot make multi-hop route without segment keys".to_string());
+    // The exit of a speed test sends the probe straight back, so that the padding crosses the
+    // route twice
+    fn handle_route_probe(
+        &self,
+        remaining_route: Route,
+        nonce: u64,
+        originator: &PublicKey,
+        padding: PlainData,
+    ) {
+        if !self.bandwidth_benchmark {
+            debug!(
+                self.logger,
+                "Ignoring speed-test probe from {}: benchmarking is off", originator
+            );
+            return;
         }
-
-        let has_long_segment = segments.iter().any(|segment| segment.keys.len() > 2);
-        if self.consuming_wallet_opt.is_none() && has_long_segment {
-            return Err("Cannot make multi-hop route segment without consuming wallet".to_string());
+        match IncipientCoresPackage::new(
+            self.cryptde,
+            remaining_route,
+            MessageType::Benchmark(Benchmark_0v1::RouteEcho { nonce, padding }.into()),
+            originator,
+        ) {
+            Ok(package) => self
+                .hopper
+                .as_ref()
+                .expect("unbound hopper")
+                .try_send(package)
+                .expect("hopper is dead"),
+            Err(e) => warning!(
+                self.logger,
+                "Couldn't echo speed-test probe from {}: {}",
+                originator,
+                e
+            ),
         }
+    }
 
-        let expected_request_services = match self.make_expected_services(&over) {
-            Ok(services) => services,
-            Err(e) => return Err(e),
+    fn handle_route_echo(&mut self, nonce: u64) {
+        let (test_id, leg, padded, sent) = match self.speed_test_probes.remove(&nonce) {
+            Some(probe) => probe,
+            None => return,
         };
-
-        let expected_response_services = match self.make_expected_services(&back) {
-            Ok(services) => services,
-            Err(e) => return Err(e),
+        let follow_up = match self
+            .speed_tests
+            .get_mut(&test_id)
+            .and_then(|speed_test| speed_test.legs.get_mut(leg))
+        {
+            // The padding went to the exit and came back again
+            Some(leg) => leg.record_round_trip(padded, sent.elapsed(), 2 * BENCHMARK_PROBE_BYTES),
+            None => return,
         };
-
-        let return_route_id = self.advance_return_route_id();
-        Ok(RouteQueryResponse {
-            route: Route::round_trip(
-                over,
-                back,
-                self.cryptde,
-                self.consuming_wallet_opt.clone(),
-                return_route_id,
-                Some(contract_address(self.chain_id)),
-            )
-            .expect("Internal error: bad route"),
-            expected_services: ExpectedServices::RoundTrip(
-                expected_request_services,
-                expected_response_services,
-                return_route_id,
-            ),
-        })
+        if follow_up {
+            self.send_speed_test_probe(test_id, leg, true);
+        } else if self
+            .speed_tests
+            .get(&test_id)
+            .map(SpeedTest::is_finished)
+            .unwrap_or(false)
+        {
+            self.finish_speed_test(test_id);
+        }
     }
 
-    fn make_route_segment(
-        &self,
-        origin: &PublicKey,
-        target: Option<&PublicKey>,
-        minimum_hop_count: usize,
-        target_component: Component,
-        direction: RouteDirection,
-    ) -> Result<RouteSegment, String> {
-        let mut node_seqs =
-            self.complete_routes(vec![origin], target, minimum_hop_count, direction);
-
-        if node_seqs.is_empty() {
-            let target_str = match target {
-                Some(t) => format!(" {}", t),
-                None => String::from("Unknown"),
-            };
-            Err(format!(
-                "Couldn't find any routes: at least {}-hop from {} to {:?} at {}",
-                minimum_hop_count, origin, target_component, target_str
-            ))
-        } else {
-            // When the target is Some all exit nodes will be the target and it is not optimal to sort.
-            if target.is_none() {
-                self.sort_routes_by_desirable_exit_nodes(node_seqs.as_mut());
-            }
-            let chosen_node_seq = node_seqs.remove(0);
-            Ok(RouteSegment::new(chosen_node_seq, target_component))
-        }
+    fn send_benchmark(&self, destination: &PublicKey, benchmark: Benchmark_0v1) {
+        let route = self.create_single_hop_route(destination);
+        let package = IncipientCoresPackage::new(
+            self.cryptde,
+            route,
+            MessageType::Benchmark(benchmark.into()),
+            destination,
+        )
+        .expect("Key magically disappeared");
+        self.hopper
+            .as_ref()
+            .expect("unbound hopper")
+            .try_send(package)
+            .expect("hopper is dead");
     }
 
-    fn sort_routes_by_desirable_exit_nodes(&self, node_seqs: &mut Vec<Vec<&PublicKey>>) {
-        if node_seqs.is_empty() {
-            panic!("Unable to sort routes by desirable exit nodes: Missing routes.");
-        }
-        let get_the_exit_nodes_desirable_flag = |vec: &Vec<&PublicKey>| -> Option<bool> {
-            vec.last()
-                .map(|pk|
+    fn to_node_descriptors(&self, keys: &[PublicKey]) -> Vec<NodeDescriptor> {
+        keys.iter()
+            .map(|k| {
+                NodeDescriptor::from((
                     self.neighborhood_database
-                        .node_by_key(pk)
-                        .unwrap_or_else(|| panic!("Unable to sort routes by desirable exit nodes: Missing NodeRecord for public key: [{}]", pk))
-                ).map(|node| node.is_desirable())
-        };
-
-        node_seqs.sort_by(|vec1: &Vec<&PublicKey>, vec2: &Vec<&PublicKey>| {
-            if vec1.is_empty() || vec2.is_empty() {
-                panic!("Unable to sort routes by desirable exit nodes: Missing route segments.")
-            }
-            let is_desirable1 = get_the_exit_nodes_desirable_flag(vec1);
-            let is_desirable2 = get_the_exit_nodes_desirable_flag(vec2);
-            match (is_desirable1, is_desirable2) {
-                (Some(true), Some(false)) => Ordering::Less,
-                (Some(false), Some(true)) => Ordering::Greater,
-                _ => Ordering::Equal,
-            }
-        });
+                        .node_by_key(k)
+                        .expect("Node disappeared"),
+                    self.chain_id == chain_id_from_name(DEFAULT_CHAIN_NAME),
+                    self.cryptde,
+                ))
+            })
+            .collect()
     }
 
-    fn make_expected_services(
-        &self,
-        segment: &RouteSegment,
-    ) -> Result<Vec<ExpectedService>, String> {
-        segment
-            .keys
+    fn handle_gossip_agrs(&mut self, agrs: Vec<AccessibleGossipRecord>, gossip_source: SocketAddr) {
+        let offered_count = agrs.len();
+        let agrs = self.refuse_impersonated_neighbors(agrs);
+        if agrs.is_empty() && offered_count > 0 {
+            return;
+        }
+        let successions = agrs
             .iter()
-            .map(|ref key| {
-                self.calculate_expected_service(key, segment.keys.first(), segment.keys.last())
+            .filter_map(|agr| {
+                agr.inner
+                    .key_succession_opt
+                    .clone()
+                    .map(|succession| (agr.inner.public_key.clone(), succession))
             })
-            .collect()
+            .collect_vec();
+        // What each Node earned into before this Gossip, since that's the only wallet it can move
+        let wallet_changes = agrs
+            .iter()
+            .filter_map(|agr| {
+                agr.inner.wallet_change_opt.clone().map(|wallet_change| {
+                    (
+                        agr.inner.public_key.clone(),
+                        self.neighborhood_database
+                            .node_by_key(&agr.inner.public_key)
+                            .map(|node| node.earning_wallet()),
+                        agr.inner.earning_wallet.clone(),
+                        wallet_change,
+                    )
+                })
+            })
+            .collect_vec();
+        let attestation_sets = agrs
+            .iter()
+            .filter(|agr| self.heed_attestations && !agr.inner.attestations.is_empty())
+            .map(|agr| {
+                (
+                    agr.inner.public_key.clone(),
+                    agr.inner.earning_wallet.clone(),
+                    agr.inner.attestations.clone(),
+                )
+            })
+            .collect_vec();
+        let gossip_keys = agrs
+            .iter()
+            .map(|agr| agr.inner.public_key.clone())
+            .collect_vec();
+        let neighbor_keys_before = self.neighbor_keys();
+        self.handle_agrs(agrs, gossip_source);
+        self.retire_superseded_keys(successions);
+        self.report_wallet_changes(wallet_changes);
+        self.take_attestations(attestation_sets);
+        self.note_peer_features(&gossip_keys);
+        let neighbor_keys_after = self.neighbor_keys();
+        self.handle_database_changes(&neighbor_keys_before, &neighbor_keys_after);
     }
 
-    fn calculate_expected_service(
+    // The operator was told which earning wallet signed each neighbor's descriptor; a Node with
+    // that key earning into some other wallet isn't the one the signer vouched for
+    fn refuse_impersonated_neighbors(
         &self,
-        route_segment_key: &PublicKey,
-        originator_key: Option<&PublicKey>,
-        exit_key: Option<&PublicKey>,
-    ) -> Result<ExpectedService, String> {
-        match self.neighborhood_database.node_by_key(route_segment_key) {
-            Some(node) => {
-                if route_segment_key == self.neighborhood_database.root().public_key() {
-                    Ok(ExpectedService::Nothing)
-                } else {
-                    match (originator_key, exit_key) {
-                        (Some(originator_key), Some(exit_key))
-                            if route_segment_key == originator_key
-                                || route_segment_key == exit_key =>
-                        {
-                            Ok(ExpectedService::Exit(
-                                route_segment_key.clone(),
-                                node.earning_wallet(),
-                                node.rate_pack().clone(),
-                            ))
-                        }
-                        (Some(_), Some(_)) => Ok(ExpectedService::Routing(
-                            route_segment_key.clone(),
-                            node.earning_wallet(),
-                            node.rate_pack().clone(),
-                        )),
-                        _ => Err(
-                            "cannot calculate expected service, no keys provided in route segment"
-                                .to_string(),
-                        ),
-                    }
+        agrs: Vec<AccessibleGossipRecord>,
+    ) -> Vec<AccessibleGossipRecord> {
+        agrs.into_iter()
+            .filter(|agr| match self.neighbor_signers.get(&agr.inner.public_key) {
+                Some(signer) if signer != &agr.inner.earning_wallet => {
+                    warning!(
+                        self.logger,
+                        "Node {} earns into wallet {}, but its descriptor was signed by {}; it may be an impersonation, so its record is refused",
+                        agr.inner.public_key,
+                        Redacted(&agr.inner.earning_wallet),
+                        Redacted(signer)
+                    );
+                    false
                 }
-            }
-            None => Err("Cannot make multi_hop with unknown neighbor".to_string()),
-        }
+                _ => true,
+            })
+            .collect()
     }
 
-    fn route_length_qualifies(&self, hops_remaining: usize) -> bool {
-        hops_remaining == 0
+    fn neighbor_keys(&self) -> Vec<PublicKey> {
+        self.neighborhood_database
+            .root()
+            .full_neighbor_keys(&self.neighborhood_database)
+            .into_iter()
+            .cloned()
+            .collect()
     }
 
-    fn last_key_qualifies(
-        &self,
-        last_node_ref: &NodeRecord,
-        target_key_ref_opt: Option<&PublicKey>,
-    ) -> bool {
-        match target_key_ref_opt {
-            Some(target_key_ref) => last_node_ref.public_key() == target_key_ref,
-            None => true,
+    fn handle_agrs(&mut self, agrs: Vec<AccessibleGossipRecord>, gossip_source: SocketAddr) {
+        let ignored_node_name = self.gossip_source_name(&agrs, gossip_source);
+        let gossip_record_count = agrs.len();
+        let source_wallet_opt = self.gossip_source_wallet_opt(gossip_source);
+        if let Some(source_wallet) = source_wallet_opt.as_ref() {
+            let score = TRUST_CACHE.score(source_wallet);
+            if score < GOSSIP_TRUST_FLOOR {
+                info!(
+                    self.logger,
+                    "Gossip from {} ignored: its earning wallet's trust score is {}",
+                    gossip_source,
+                    score
+                );
+                self.handle_gossip_ignored(ignored_node_name, gossip_record_count);
+                return;
+            }
+        }
+        let acceptance_result =
+            self.gossip_acceptor
+                .handle(&mut self.neighborhood_database, agrs, gossip_source);
+        match acceptance_result {
+            GossipAcceptanceResult::Accepted => {
+                self.add_trust_evidence(source_wallet_opt, TrustEvidence::AccurateGossip);
+                self.prune_excess_neighbors();
+                self.gossip_to_neighbors()
+            }
+            GossipAcceptanceResult::Reply(next_debut, target_key, target_node_addr) => {
+                self.handle_gossip_reply(next_debut, &target_key, &target_node_addr)
+            }
+            GossipAcceptanceResult::Failed(failure, target_key, target_node_addr) => {
+                self.handle_gossip_failed(failure, &target_key, &target_node_addr)
+            }
+            GossipAcceptanceResult::Ignored => {
+                trace!(self.logger, "Gossip from {} ignored", gossip_source);
+                self.handle_gossip_ignored(ignored_node_name, gossip_record_count)
+            }
+            GossipAcceptanceResult::Ban(reason) => {
+                warning!(
+                    self.logger,
+                    "Malefactor detected at {}; counting it against its trust and ignoring: {}",
+                    gossip_source,
+                    reason
+                );
+                self.add_trust_evidence(source_wallet_opt, TrustEvidence::InaccurateGossip);
+                self.handle_gossip_ignored(ignored_node_name, gossip_record_count);
+            }
         }
     }
 
-    fn validate_last_node_not_too_close_to_first_node(
-        &self,
-        prefix_len: usize,
-        first_node_key: &PublicKey,
-        candidate_node_key: &PublicKey,
-    ) -> bool {
-        if prefix_len <= 2 {
-            true // Zero- and single-hop routes are not subject to exit-too-close restrictions
-        } else {
-            !self
-                .neighborhood_database
-                .has_half_neighbor(candidate_node_key, first_node_key)
-        }
+    // Gossip we relay for ourselves, or from a Node we haven't heard of yet, has nobody to trust
+    fn gossip_source_wallet_opt(&self, gossip_source: SocketAddr) -> Option<Wallet> {
+        let db = &self.neighborhood_database;
+        db.node_by_ip(&gossip_source.ip())
+            .filter(|node| node.public_key() != db.root().public_key())
+            .map(|node| node.earning_wallet())
     }
 
-    fn is_orig_node_on_back_leg(
-        node: &NodeRecord,
-        target_key_opt: Option<&PublicKey>,
-        direction: RouteDirection,
-    ) -> bool {
-        match direction {
-            RouteDirection::Over => false,
-            RouteDirection::Back => match target_key_opt {
-                None => false,
-                Some(target_key) => node.public_key() == target_key,
-            },
+    fn add_trust_evidence(&self, wallet_opt: Option<Wallet>, evidence: TrustEvidence) {
+        if let (Some(wallet), Some(dao)) = (wallet_opt, self.trust_dao_opt.as_ref()) {
+            dao.add_evidence(&wallet, evidence)
         }
     }
 
-    fn advance_return_route_id(&mut self) -> u32 {
-        let return_route_id = self.next_return_route_id;
-        self.next_return_route_id = return_route_id.wrapping_add(1);
-        return_route_id
+    // Only records the acceptor let into the database are believed
+    fn note_peer_features(&self, gossip_keys: &[PublicKey]) {
+        gossip_keys.iter().for_each(|key| {
+            if let Some(node) = self.neighborhood_database.node_by_key(key) {
+                cipher_suite::record_peer_features(key, node.features());
+                hybrid_kem::record_peer_kem_key(
+                    key,
+                    node.kem_public_key_opt().map(|kem_key| kem_key.as_slice()),
+                );
+            }
+        });
     }
 
-    // Main recursive routing engine. Supply origin key as single-element vector in prefix,
-    // target key, if any, in target, and minimum hop count in hops_remaining. Return value is
-    // a list of all the node sequences that will either go from the origin to the target in
-    // hops_remaining or more hops with no cycles, or from the origin hops_remaining hops out into
-    // the MASQ Network. No round trips; if you want a round trip, call this method twice.
-    // If the return value is empty, no qualifying route was found.
-    fn complete_routes<'a>(
-        &'a self,
-        prefix: Vec<&'a PublicKey>,
-        target_opt: Option<&'a PublicKey>,
-        hops_remaining: usize,
-        direction: RouteDirection,
-    ) -> Vec<Vec<&'a PublicKey>> {
-        let first_node_key = prefix.first().expect("Empty prefix");
-        let previous_node = self
-            .neighborhood_database
-            .node_by_key(prefix.last().expect("Empty prefix"))
-            .expect("Last Node magically disappeared");
-        // Check to see if we're done. If we are, all three of these qualifications will pass.
-        if self.route_length_qualifies(hops_remaining)
-            && self.last_key_qualifies(previous_node, target_opt)
-            && self.validate_last_node_not_too_close_to_first_node(
-                prefix.len(),
-                *first_node_key,
-                previous_node.public_key(),
-            )
-        {
-            vec![prefix]
-        } else if (hops_remaining == 0) && target_opt.is_none() {
-            // don't continue a targetless search past the minimum hop count
-            vec![]
-        } else {
-            // Go through all the neighbors and compute shorter routes through all the ones we're not already using.
-            previous_node
-                .full_neighbors(&self.neighborhood_database)
-                .iter()
-                .filter(|node_record| !prefix.contains(&node_record.public_key()))
-                .filter(|node_record| {
-                    node_record.routes_data()
-                        || Self::is_orig_node_on_back_leg(**node_record, target_opt, direction)
-                })
-                .flat_map(|node_record| {
-                    let mut new_prefix = prefix.clone();
-                    new_prefix.push(node_record.public_key());
-
-                    let new_hops_remaining = if hops_remaining == 0 {
-                        0
-                    } else {
-                        hops_remaining - 1
-                    };
-
-                    self.complete_routes(
-                        new_prefix.clone(),
-                        target_opt,
-                        new_hops_remaining,
-                        direction,
-                    )
-                })
-                .collect()
-        }
+    // A Node that has rotated its key is the same Node it was under its previous key; once we know
+    // the new key, and the previous key vouches for it, the Node shouldn't be in the database twice
+    fn retire_superseded_keys(&mut self, successions: Vec<(PublicKey, KeySuccession)>) {
+        successions
+            .into_iter()
+            .for_each(|(current_key, succession)| {
+                let previous_key = &succession.previous_public_key;
+                let db = &mut self.neighborhood_database;
+                if previous_key == db.root().public_key()
+                    || db.node_by_key(&current_key).is_none()
+                    || db.node_by_key(previous_key).is_none()
+                {
+                    return;
+                }
+                if !succession.is_endorsed(self.cryptde, &current_key) {
+                    warning!(
+                        self.logger,
+                        "Node {} claims to succeed {}, but the endorsement is not valid; ignoring",
+                        current_key,
+                        previous_key
+                    );
+                    return;
+                }
+                if db.root_mut().remove_half_neighbor_key(previous_key) {
+                    db.add_half_neighbor(&current_key)
+                        .expect("New key disappeared from the database");
+                    db.root_mut().increment_version();
+                    db.root_mut().regenerate_signed_gossip(self.cryptde);
+                }
+                db.remove_node(previous_key);
+                info!(
+                    self.logger,
+                    "Node {} has rotated its key to {}", previous_key, current_key
+                );
+            });
     }
 
-    fn handle_gossip_reply(
-        &self,
-        gossip: Gossip_0v1,
-        target_key: &PublicKey,
-        target_node_addr: &NodeAddr,
+    // Only a change the Node signed for itself, about the wallet the database now has for it, is
+    // worth the Accountant's attention, and then only once per effective block
+    fn report_wallet_changes(
+        &mut self,
+        wallet_changes: Vec<(PublicKey, Option<Wallet>, Wallet, WalletChange)>,
     ) {
-        self.send_no_lookup_package(
-            MessageType::Gossip(gossip.clone().into()),
-            target_key,
-            target_node_addr,
-        );
-        trace!(
-            self.logger,
-            "Sent Gossip: {}",
-            gossip.to_dot_graph(
-                self.neighborhood_database.root(),
-                (target_key, &Some(target_node_addr.clone())),
-            )
+        wallet_changes.into_iter().for_each(
+            |(public_key, wallet_before_opt, current_wallet, wallet_change)| {
+                if &public_key == self.neighborhood_database.root().public_key() {
+                    return;
+                }
+                match self.neighborhood_database.node_by_key(&public_key) {
+                    Some(node) if node.earning_wallet() == current_wallet => (),
+                    _ => return,
+                }
+                if let Some(reported_block) = self.wallet_changes_reported.get(&public_key) {
+                    if wallet_change.effective_block <= *reported_block {
+                        return;
+                    }
+                }
+                if wallet_before_opt.as_ref() != Some(&wallet_change.previous_wallet) {
+                    warning!(
+                        self.logger,
+                        "Node {} claims to have changed its earning wallet from {}, which it never earned into; ignoring",
+                        public_key,
+                        Redacted(&wallet_change.previous_wallet)
+                    );
+                    return;
+                }
+                if !wallet_change.is_signed(&current_wallet) {
+                    warning!(
+                        self.logger,
+                        "Node {} claims to have changed its earning wallet, but the old wallet didn't sign the change; ignoring",
+                        public_key
+                    );
+                    return;
+                }
+                info!(
+                    self.logger,
+                    "Node {} earns into wallet {} instead of {} as of block {}",
+                    public_key,
+                    Redacted(&current_wallet),
+                    Redacted(&wallet_change.previous_wallet),
+                    wallet_change.effective_block
+                );
+                self.wallet_changes_reported
+                    .insert(public_key, wallet_change.effective_block);
+                self.report_earning_wallet_change_sub
+                    .as_ref()
+                    .expect("Accountant is unbound")
+                    .try_send(ReportEarningWalletChangeMessage {
+                        previous_wallet: wallet_change.previous_wallet,
+                        current_wallet,
+                        effective_block: wallet_change.effective_block,
+                    })
+                    .expect("Accountant is dead");
+            },
         );
     }
 
-    fn handle_gossip_failed(
-        &self,
-        gossip_failure: GossipFailure_0v1,
-        target_key: &PublicKey,
-        target_node_addr: &NodeAddr,
-    ) {
-        self.send_no_lookup_package(
-            MessageType::GossipFailure(VersionedData::new(
-                &crate::sub_lib::migrations::gossip_failure::MIGRATIONS,
-                &gossip_failure,
-            )),
-            target_key,
-            target_node_addr,
-        );
-        trace!(self.logger, "Sent GossipFailure_0v1: {}", gossip_failure);
+    // Attestations are only as good as their attestor: it must have signed all of them, there
+    // mustn't be too many of them or too many changes to them, and this Node mustn't distrust it
+    fn take_attestations(&mut self, attestation_sets: Vec<(PublicKey, Wallet, Vec<Attestation>)>) {
+        attestation_sets
+            .into_iter()
+            .for_each(|(public_key, attestor, attestations)| {
+                if &public_key == self.neighborhood_database.root().public_key() {
+                    return;
+                }
+                match self.neighborhood_database.node_by_key(&public_key) {
+                    Some(node) if node.earning_wallet() == attestor => (),
+                    _ => return,
+                }
+                if attestations.len() > MAX_PUBLISHED_ATTESTATIONS {
+                    warning!(
+                        self.logger,
+                        "Node {} published {} attestations, more than the {} allowed; ignoring them",
+                        public_key,
+                        attestations.len(),
+                        MAX_PUBLISHED_ATTESTATIONS
+                    );
+                    return;
+                }
+                if !attestations
+                    .iter()
+                    .all(|attestation| attestation.is_signed(self.cryptde, &public_key, &attestor))
+                {
+                    warning!(
+                        self.logger,
+                        "Node {} published attestations whose signatures are not valid; ignoring them",
+                        public_key
+                    );
+                    return;
+                }
+                let score = TRUST_CACHE.score(&attestor);
+                if score < NEUTRAL_TRUST_SCORE {
+                    debug!(
+                        self.logger,
+                        "Attestations from Node {} ignored: its earning wallet's trust score is {}",
+                        public_key,
+                        score
+                    );
+                    return;
+                }
+                let count = attestations.len();
+                if ATTESTATION_CACHE.accept(&attestor, attestations, Instant::now()) {
+                    debug!(
+                        self.logger,
+                        "Took {} attestations from Node {}", count, public_key
+                    );
+                }
+            });
     }
 
-    fn handle_gossip_ignored(&self, _ignored_node_name: String, _gossip_record_count: usize) {
-        // Maybe something here eventually for keeping statistics
+    // Tells the rest of the Network what this Node has seen of the wallets that owed it payments
+    fn attest_to_payments(&mut self) {
+        let attestations = make_attestations(
+            TRUST_CACHE.records(),
+            &self.neighborhood_database.root().earning_wallet(),
+            self.cryptde,
+        );
+        let root = self.neighborhood_database.root_mut();
+        if root.set_attestations(attestations) {
+            root.increment_version();
+            self.gossip_to_neighbors();
+        }
     }
 
-    fn send_no_lookup_package(
-        &self,
-        message_type: MessageType,
-        target_key: &PublicKey,
-        target_node_addr: &NodeAddr,
+    fn handle_database_changes(
+        &mut self,
+        neighbor_keys_before: &[PublicKey],
+        neighbor_keys_after: &[PublicKey],
     ) {
-        let package = match NoLookupIncipientCoresPackage::new(
-            self.cryptde,
-            target_key,
-            target_node_addr,
-            message_type,
-        ) {
-            Ok(p) => p,
-            Err(e) => {
-                error!(self.logger, "{}", e);
-                return;
+        metrics::NEIGHBORS.set(neighbor_keys_after.len() as u64);
+        metrics::NEIGHBORS_GAINED.add(
+            neighbor_keys_after
+                .iter()
+                .filter(|key| !neighbor_keys_before.contains(key))
+                .count() as u64,
+        );
+        metrics::NEIGHBORS_LOST.add(
+            neighbor_keys_before
+                .iter()
+                .filter(|key| !neighbor_keys_after.contains(key))
+                .count() as u64,
+        );
+        self.curate_past_neighbors(neighbor_keys_before, neighbor_keys_after);
+        self.broadcast_neighbor_changes(neighbor_keys_before, neighbor_keys_after);
+        let routes_available = self.check_connectedness();
+        report_neighborhood_readiness(NeighborhoodReadiness {
+            connected: !neighbor_keys_after.is_empty(),
+            routes_available,
+        });
+        self.broadcast_warm_up(neighbor_keys_after.len(), routes_available);
+        if self.dump_neighborhood {
+            self.dump_neighborhood_graph();
+        }
+    }
+
+    fn dump_neighborhood_graph(&self) {
+        let graph = self.neighborhood_graph();
+        let dumps = vec![
+            (NEIGHBORHOOD_JSON_FILE, graph_snapshot(&graph)),
+            (NEIGHBORHOOD_DOT_FILE, graph.dot_graph),
+        ];
+        dumps.into_iter().for_each(|(filename, contents)| {
+            let path = self.data_directory.join(filename);
+            if let Err(e) = fs::write(&path, contents) {
+                warning!(
+                    self.logger,
+                    "Can't dump the neighborhood into {}: {}",
+                    path.display(),
+                    e
+                )
             }
+        });
+    }
+
+    // Only while the Neighborhood is first getting to where it can route, and only when something
+    // has changed, so that UIs can show a progress bar instead of what looks like a dead Node
+    fn broadcast_warm_up(&mut self, neighbor_count: usize, routes_available: bool) {
+        if let Some(UiWarmUpBroadcast {
+            routes_available: true,
+            ..
+        }) = self.last_warm_up_opt
+        {
+            return;
+        }
+        let ui_message_sub = match self.to_ui_message_sub.as_ref() {
+            Some(ui_message_sub) => ui_message_sub,
+            None => return,
         };
-        self.hopper_no_lookup
-            .as_ref()
-            .expect("No-lookup Hopper is unbound")
-            .try_send(package)
-            .expect("Hopper is dead");
+        let warm_up = warm_up_progress(
+            self.neighborhood_database.keys().len() - 1,
+            neighbor_count,
+            routes_available,
+        );
+        if self.last_warm_up_opt.as_ref() == Some(&warm_up) {
+            return;
+        }
+        ui_message_sub
+            .try_send(NodeToUiMessage {
+                target: AllClients,
+                body: warm_up.clone().tmb(0),
+            })
+            .expect("UiGateway is dead");
+        self.last_warm_up_opt = Some(warm_up);
     }
 
-    fn gossip_source_name(
+    // Gossip can arrive before the UiGateway is bound; nobody can have subscribed yet then
+    fn broadcast_neighbor_changes(
         &self,
-        accessible_gossip: &[AccessibleGossipRecord],
-        gossip_source: SocketAddr,
-    ) -> String {
-        match accessible_gossip.iter().find(|agr| {
-            if let Some(ref node_addr) = agr.node_addr_opt {
-                node_addr.ip_addr() == gossip_source.ip()
+        neighbor_keys_before: &[PublicKey],
+        neighbor_keys_after: &[PublicKey],
+    ) {
+        let ui_message_sub = match self.to_ui_message_sub.as_ref() {
+            Some(ui_message_sub) => ui_message_sub,
+            None => return,
+        };
+        let added = neighbor_keys_after
+            .iter()
+            .filter(|key| !neighbor_keys_before.contains(key))
+            .map(|key| key.to_string())
+            .collect_vec();
+        let removed = neighbor_keys_before
+            .iter()
+            .filter(|key| !neighbor_keys_after.contains(key))
+            .map(|key| key.to_string())
+            .collect_vec();
+        if added.is_empty() && removed.is_empty() {
+            return;
+        }
+        ui_message_sub
+            .try_send(NodeToUiMessage {
+                target: AllClients,
+                body: UiNeighborsChangedBroadcast { added, removed }.tmb(0),
+            })
+            .expect("UiGateway is dead");
+    }
+
+    fn curate_past_neighbors(
+        &mut self,
+        neighbor_keys_before: &[PublicKey],
+        neighbor_keys_after: &[PublicKey],
+    ) {
+        if neighbor_keys_after != neighbor_keys_before {
+            if let Some(db_password) = &self.db_password_opt {
+                let nds = self.to_node_descriptors(neighbor_keys_after);
+                let node_descriptors_opt = if nds.is_empty() {
+                    None
+                } else {
+                    Some(nds.into_iter().collect_vec())
+                };
+                debug!(
+                    self.logger,
+                    "Saving neighbor list: {:?}",
+                    Redacted(&node_descriptors_opt)
+                );
+                match self
+                    .persistent_config_opt
+                    .as_mut()
+                    .expect("PersistentConfig was not set by StartMessage")
+                    .set_past_neighbors(node_descriptors_opt, db_password)
+                {
+                    Ok(_) => info!(self.logger, "Persisted neighbor changes for next run"),
+                    Err(e) => error!(
+                        self.logger,
+                        "Could not persist immediate-neighbor changes: {:?}", e
+                    ),
+                };
             } else {
-                false
+                info!(self.logger, "Declining to persist neighbor changes for next run: no database password supplied")
             }
-        }) {
-            Some(agr) => format!("{}", agr.inner.public_key),
-            None => format!("{}", gossip_source),
+        } else {
+            debug!(self.logger, "No neighbor changes; database is unchanged")
         }
     }
 
-    fn handle_stream_shutdown_msg(&mut self, msg: StreamShutdownMsg) {
-        if msg.stream_type != RemovedStreamType::Clandestine {
-            panic!("Neighborhood should never get ShutdownStreamMsg about non-clandestine stream")
-        }
-        let neighbor_key = match self.neighborhood_database.node_by_ip(&msg.peer_addr.ip()) {
-            None => {
-                warning!(self.logger, "Received shutdown notification for stream to {}, but no Node with that IP is in the database - ignoring", msg.peer_addr.ip());
-                return;
-            }
-            Some(n) => (n.public_key().clone()),
+    // Tells whether a route can be made now. Once one first can, the Accountant is started;
+    // after that, losing every route is no error, so it isn't logged as one.
+    fn check_connectedness(&mut self) -> bool {
+        let msg = RouteQueryMessage {
+            target_key_opt: None,
+            target_component: Component::ProxyClient,
+            minimum_hop_count: DEFAULT_MINIMUM_HOP_COUNT,
+            return_component_opt: Some(Component::ProxyServer),
+            exit_target_opt: None,
+            avoided_keys: vec![],
         };
-        self.remove_neighbor(&neighbor_key, &msg.peer_addr);
+        if self.is_connected {
+            return self.make_round_trip_route(msg).is_ok();
+        }
+        if self.handle_route_query_message(msg).is_some() {
+            self.is_connected = true;
+            self.connected_signal
+                .as_ref()
+                .expect("Accountant was not bound")
+                .try_send(StartMessage {})
+                .expect("Accountant is dead");
+            true
+        } else {
+            false
+        }
     }
 
-    fn remove_neighbor(&mut self, neighbor_key: &PublicKey, peer_addr: &SocketAddr) {
-        match self.neighborhood_database.remove_neighbor(neighbor_key) {
-            Err(e) => panic!("Node suddenly disappeared: {:?}", e),
-            Ok(true) => {
-                debug!(
+    fn announce_gossip_handling_completion(&self, record_count: usize) {
+        info!(
+            self.logger,
+            "Finished processing Gossip about {} Nodes", record_count
+        );
+    }
+
+    // Gossip can leave this Node with more neighbors than MAX_DEGREE. The ones it drops are those
+    // whose earning wallets have paid least, then been charged least, for the traffic it carried;
+    // among equals, the best-connected go first, since they lose least by it.
+    fn prune_excess_neighbors(&mut self) {
+        let neighbor_keys = self
+            .neighborhood_database
+            .root()
+            .half_neighbor_keys()
+            .into_iter()
+            .cloned()
+            .collect::<Vec<PublicKey>>();
+        if neighbor_keys.len() <= MAX_DEGREE {
+            return;
+        }
+        let excess = neighbor_keys.len() - MAX_DEGREE;
+        let database = &self.neighborhood_database;
+        let doomed = neighbor_keys
+            .into_iter()
+            .sorted_by_key(|key| {
+                let earnings = database
+                    .node_by_key(key)
+                    .map(|node| EARNINGS_CACHE.earnings(&node.earning_wallet()))
+                    .unwrap_or_default();
+                (earnings, Reverse(database.gossip_target_degree(key)))
+            })
+            .take(excess)
+            .collect::<Vec<PublicKey>>();
+        doomed.into_iter().for_each(|key| {
+            match self.neighborhood_database.remove_neighbor(&key) {
+                Ok(_) => info!(
                     self.logger,
-                    "Received shutdown notification for {} at {}: removing neighborship",
-                    neighbor_key,
-                    peer_addr.ip()
-                );
-                self.gossip_to_neighbors()
+                    "Pruned neighbor {}: more than {} neighbors, and it has earned this Node the least",
+                    key,
+                    MAX_DEGREE
+                ),
+                Err(e) => error!(self.logger, "{}", e),
             }
-            Ok(false) => {
-                debug!(self.logger, "Received shutdown notification for {} at {}, but that Node is no neighbor - ignoring", neighbor_key, peer_addr.ip());
+        });
+    }
+
+    fn gossip_to_neighbors(&mut self) {
+        self.neighborhood_database
+            .root_mut()
+            .regenerate_signed_gossip(self.cryptde);
+        let neighbors = self
+            .neighborhood_database
+            .root()
+            .half_neighbor_keys()
+            .into_iter()
+            .cloned()
+            .collect_vec();
+        neighbors.iter().for_each(|neighbor| {
+            if let Some(gossip) = self
+                .gossip_producer
+                .produce(&mut self.neighborhood_database, neighbor)
+            {
+                self.gossip_to_neighbor(neighbor, gossip)
             }
-        };
+        });
     }
 
-    #[allow(unreachable_code)]
-    fn handle_shutdown_order(&self, client_id: u64, _msg: UiShutdownRequest) {
+    fn gossip_to_neighbor(&self, neighbor: &PublicKey, gossip: Gossip_0v1) {
+        let gossip_len = gossip.node_records.len();
+        let route = self.create_single_hop_route(neighbor);
+        let package =
+            IncipientCoresPackage::new(self.cryptde, route, gossip.clone().into(), neighbor)
+                .expect("Key magically disappeared");
         info!(
             self.logger,
-            "Received shutdown order from client {}: shutting down hard", client_id
+            "Sending update Gossip about {} Nodes to Node {}", gossip_len, neighbor
         );
-        exit_process(
-            0,
-            &format!(
-                "Received shutdown order from client {}: shutting down hard",
-                client_id
-            ),
+        self.hopper
+            .as_ref()
+            .expect("unbound hopper")
+            .try_send(package)
+            .expect("hopper is dead");
+        metrics::GOSSIP_SENT.add(1);
+        trace!(
+            self.logger,
+            "Sent Gossip: {}",
+            gossip.to_dot_graph(
+                self.neighborhood_database.root(),
+                self.neighborhood_database
+                    .node_by_key(&neighbor)
+                    .expect("Node magically disappeared"),
+            )
         );
     }
-}
-
-pub fn regenerate_signed_gossip(
-    inner: &NodeRecordInner_0v1,
-    cryptde: &dyn CryptDE, // Must be the correct CryptDE for the Node from which inner came: used for signing
-) -> (PlainData, CryptData) {
-    let signed_gossip =
-        PlainData::from(serde_cbor::ser::to_vec(&inner).expect("Serialization failed"));
-    let signature = match cryptde.sign(&signed_gossip) {
-        Ok(sig) => sig,
-        Err(e) => unimplemented!("TODO: Signing error: {:?}", e),
-    };
-    (signed_gossip, signature)
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::blockchain::blockchain_interface::{chain_id_from_name, contract_address};
-    use crate::db_config::persistent_configuration::PersistentConfigError;
-    use crate::neighborhood::gossip::GossipBuilder;
-    use crate::neighborhood::gossip::Gossip_0v1;
-    use crate::neighborhood::node_record::NodeRecordInner_0v1;
-    use crate::stream_messages::{NonClandestineAttributes, RemovedStreamType};
-    use crate::sub_lib::cryptde::{decodex, encodex, CryptData};
-    use crate::sub_lib::cryptde_null::CryptDENull;
-    use crate::sub_lib::dispatcher::Endpoint;
+    fn create_single_hop_route(&self, destination: &PublicKey) -> Route {
+        Route::one_way(
+            RouteSegment::new(
+                vec![&self.cryptde.public_key(), destination],
+                Component::Neighborhood,
+            ),
+            self.cryptde,
+            None,
+            None,
+        )
+        .expect("route creation error")
+    }
+
+    fn zero_hop_route_response(&mut self) -> RouteQueryResponse {
+        let return_route_id = self.advance_return_route_id();
+        let route = Route::round_trip(
+            RouteSegment::new(
+                vec![&self.cryptde.public_key(), &self.cryptde.public_key()],
+                Component::ProxyClient,
+            ),
+            RouteSegment::new(
+                vec![&self.cryptde.public_key(), &self.cryptde.public_key()],
+                Component::ProxyServer,
+            ),
+            self.cryptde,
+            None,
+            return_route_id,
+            None,
+        )
+        .expect("Couldn't create route");
+        RouteQueryResponse {
+            route,
+            expected_services: ExpectedServices::RoundTrip(
+                vec![ExpectedService::Nothing, ExpectedService::Nothing],
+                vec![ExpectedService::Nothing, ExpectedService::Nothing],
+                return_route_id,
+            ),
+        }
+    }
+
+    fn make_round_trip_route(
+        &mut self,
+        msg: RouteQueryMessage,
+    ) -> Result<RouteQueryResponse, String> {
+        let over = self.make_route_segment(
+            &self.cryptde.public_key(),
+            msg.target_key_opt.as_ref(),
+            msg.minimum_hop_count,
+            msg.target_component,
+            RouteDirection::Over,
+            msg.exit_target_opt.as_ref(),
+            &msg.avoided_keys,
+        )?;
+        debug!(self.logger, "Route over: {:?}", over);
+        let back = self.make_route_segment(
+            over.keys.last().expect("Empty segment"),
+            Some(&self.cryptde.public_key()),
+            msg.minimum_hop_count,
+            msg.return_component_opt.expect("No return component"),
+            RouteDirection::Back,
+            None,
+            &msg.avoided_keys,
+        )?;
+        debug!(self.logger, "Route back: {:?}", back);
+        self.compose_route_query_response(over, back)
+    }
+
+    fn compose_route_query_response(
+        &mut self,
+        over: RouteSegment,
+        back: RouteSegment,
+    ) -> Result<RouteQueryResponse, String> {
+        let segments = vec![&over, &back];
+
+        if segments.iter().any(|rs| rs.keys.is_empty()) {
+            return Err("Cannot make multi-hop route without segment keys".to_string());
+        }
+
+        let has_long_segment = segments.iter().any(|segment| segment.keys.len() > 2);
+        if self.consuming_wallet_opt.is_none() && has_long_segment {
+            return Err("Cannot make multi-hop route segment without consuming wallet".to_string());
+        }
+
+        let expected_request_services = match self.make_expected_services(&over) {
+            Ok(services) => services,
+            Err(e) => return Err(e),
+        };
+
+        let expected_response_services = match self.make_expected_services(&back) {
+            Ok(services) => services,
+            Err(e) => return Err(e),
+        };
+
+        let return_route_id = self.advance_return_route_id();
+        let route = match &self.timing_cryptde_opt {
+            Some(timing_cryptde) => Route::timed_round_trip(
+                over,
+                back,
+                self.cryptde,
+                self.consuming_wallet_opt.clone(),
+                return_route_id,
+                Some(contract_address(self.chain_id)),
+                timing_cryptde.public_key(),
+            ),
+            None => Route::round_trip(
+                over,
+                back,
+                self.cryptde,
+                self.consuming_wallet_opt.clone(),
+                return_route_id,
+                Some(contract_address(self.chain_id)),
+            ),
+        };
+        Ok(RouteQueryResponse {
+            route: route.expect("Internal error: bad route"),
+            expected_services: ExpectedServices::RoundTrip(
+                expected_request_services,
+                expected_response_services,
+                return_route_id,
+            ),
+        })
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn make_route_segment(
+        &self,
+        origin: &PublicKey,
+        target: Option<&PublicKey>,
+        minimum_hop_count: usize,
+        target_component: Component,
+        direction: RouteDirection,
+        exit_target_opt: Option<&ExitTarget>,
+        avoided_keys: &[PublicKey],
+    ) -> Result<RouteSegment, String> {
+        let mut node_seqs =
+            self.complete_routes(vec![origin], target, minimum_hop_count, direction);
+
+        if !avoided_keys.is_empty() {
+            let route_count = node_seqs.len();
+            node_seqs.retain(|node_seq| Self::relays_avoid(node_seq, avoided_keys));
+            if route_count > 0 && node_seqs.is_empty() {
+                return Err(format!(
+                    "Couldn't find any routes: at least {}-hop from {} to {:?} without relaying through any of {} other Nodes",
+                    minimum_hop_count, origin, target_component, avoided_keys.len()
+                ));
+            }
+        }
+
+        if let Some(exit_target) = exit_target_opt {
+            let route_count = node_seqs.len();
+            node_seqs.retain(|node_seq| self.exit_node_permits(node_seq, exit_target));
+            if route_count > 0 && node_seqs.is_empty() {
+                return Err(format!(
+                    "Couldn't find any routes: at least {}-hop from {} to {:?} through an exit Node whose policy permits {}",
+                    minimum_hop_count, origin, target_component, exit_target
+                ));
+            }
+        }
+
+        if node_seqs.is_empty() {
+            let target_str = match target {
+                Some(t) => format!(" {}", t),
+                None => String::from("Unknown"),
+            };
+            Err(format!(
+                "Couldn't find any routes: at least {}-hop from {} to {:?} at {}",
+                minimum_hop_count, origin, target_component, target_str
+            ))
+        } else {
+            // Routes come out in hash order, which differs from run to run
+            if deterministic::is_deterministic() {
+                node_seqs.sort();
+            }
+            self.sort_routes_by_link_cost(node_seqs.as_mut());
+            self.sort_routes_by_trust(node_seqs.as_mut());
+            // When the target is Some all exit nodes will be the target and it is not optimal to sort.
+            if target.is_none() {
+                self.sort_routes_by_desirable_exit_nodes(node_seqs.as_mut());
+            }
+            let chosen_node_seq = node_seqs.remove(0);
+            Ok(RouteSegment::new(chosen_node_seq, target_component))
+        }
+    }
+
+    fn relays_avoid(node_seq: &[&PublicKey], avoided_keys: &[PublicKey]) -> bool {
+        node_seq.len() < 3
+            || !node_seq[1..(node_seq.len() - 1)]
+                .iter()
+                .any(|key| avoided_keys.contains(key))
+    }
+
+    fn exit_node_permits(&self, node_seq: &[&PublicKey], exit_target: &ExitTarget) -> bool {
+        match node_seq
+            .last()
+            .and_then(|key| self.neighborhood_database.node_by_key(key))
+        {
+            Some(exit_node) => exit_node
+                .exit_policy()
+                .permits(exit_target.hostname_opt.as_deref(), exit_target.port),
+            None => false,
+        }
+    }
+
+    // Routes over measured links go before routes over slower ones; without any measurements, the
+    // order is left alone
+    fn sort_routes_by_link_cost(&self, node_seqs: &mut Vec<Vec<&PublicKey>>) {
+        let link_cost_opt = |from: &PublicKey, to: &PublicKey| -> Option<u64> {
+            let db = &self.neighborhood_database;
+            db.node_by_key(from)
+                .and_then(|node| node.link_performance().get(to))
+                .or_else(|| {
+                    db.node_by_key(to)
+                        .and_then(|node| node.link_performance().get(from))
+                })
+                .map(|performance| performance.cost_ms())
+        };
+        let measured = node_seqs.iter().any(|node_seq| {
+            node_seq
+                .windows(2)
+                .any(|pair| link_cost_opt(pair[0], pair[1]).is_some())
+        });
+        if !measured {
+            return;
+        }
+        node_seqs.sort_by_key(|node_seq| {
+            node_seq
+                .windows(2)
+                .map(|pair| link_cost_opt(pair[0], pair[1]).unwrap_or(UNMEASURED_LINK_COST_MS))
+                .sum::<u64>()
+        });
+    }
+
+    // A route is as trustworthy as the least trusted Node it goes through. Scores are compared in
+    // bands of TRUST_BAND_WIDTH, so that link cost still decides between routes of about the same
+    // trust; while every Node is a stranger, the order is left alone.
+    fn sort_routes_by_trust(&self, node_seqs: &mut Vec<Vec<&PublicKey>>) {
+        let trust_band = |node_seq: &Vec<&PublicKey>| -> u8 {
+            node_seq
+                .iter()
+                .skip(1)
+                .filter_map(|key| self.neighborhood_database.node_by_key(key))
+                .map(|node| TRUST_CACHE.score(&node.earning_wallet()) / TRUST_BAND_WIDTH)
+                .min()
+                .unwrap_or(NEUTRAL_TRUST_SCORE / TRUST_BAND_WIDTH)
+        };
+        node_seqs.sort_by_key(|node_seq| Reverse(trust_band(node_seq)));
+    }
+
+    fn sort_routes_by_desirable_exit_nodes(&self, node_seqs: &mut Vec<Vec<&PublicKey>>) {
+        if node_seqs.is_empty() {
+            panic!("Unable to sort routes by desirable exit nodes: Missing routes.");
+        }
+        let get_the_exit_nodes_desirable_flag = |vec: &Vec<&PublicKey>| -> Option<bool> {
+            vec.last()
+                .map(|pk|
+                    self.neighborhood_database
+                        .node_by_key(pk)
+                        .unwrap_or_else(|| panic!("Unable to sort routes by desirable exit nodes: Missing NodeRecord for public key: [{}]", pk))
+                ).map(|node| node.is_desirable())
+        };
+
+        node_seqs.sort_by(|vec1: &Vec<&PublicKey>, vec2: &Vec<&PublicKey>| {
+            if vec1.is_empty() || vec2.is_empty() {
+                panic!("Unable to sort routes by desirable exit nodes: Missing route segments.")
+            }
+            let is_desirable1 = get_the_exit_nodes_desirable_flag(vec1);
+            let is_desirable2 = get_the_exit_nodes_desirable_flag(vec2);
+            match (is_desirable1, is_desirable2) {
+                (Some(true), Some(false)) => Ordering::Less,
+                (Some(false), Some(true)) => Ordering::Greater,
+                _ => Ordering::Equal,
+            }
+        });
+    }
+
+    fn make_expected_services(
+        &self,
+        segment: &RouteSegment,
+    ) -> Result<Vec<ExpectedService>, String> {
+        segment
+            .keys
+            .iter()
+            .map(|ref key| {
+                self.calculate_expected_service(key, segment.keys.first(), segment.keys.last())
+            })
+            .collect()
+    }
+
+    fn calculate_expected_service(
+        &self,
+        route_segment_key: &PublicKey,
+        originator_key: Option<&PublicKey>,
+        exit_key: Option<&PublicKey>,
+    ) -> Result<ExpectedService, String> {
+        match self.neighborhood_database.node_by_key(route_segment_key) {
+            Some(node) => {
+                if route_segment_key == self.neighborhood_database.root().public_key() {
+                    Ok(ExpectedService::Nothing)
+                } else {
+                    match (originator_key, exit_key) {
+                        (Some(originator_key), Some(exit_key))
+                            if route_segment_key == originator_key
+                                || route_segment_key == exit_key =>
+                        {
+                            Ok(ExpectedService::Exit(
+                                route_segment_key.clone(),
+                                node.earning_wallet(),
+                                node.rate_pack().clone(),
+                            ))
+                        }
+                        (Some(_), Some(_)) => Ok(ExpectedService::Routing(
+                            route_segment_key.clone(),
+                            node.earning_wallet(),
+                            node.rate_pack().clone(),
+                        )),
+                        _ => Err(
+                            "cannot calculate expected service, no keys provided in route segment"
+                                .to_string(),
+                        ),
+                    }
+                }
+            }
+            None => Err("Cannot make multi_hop with unknown neighbor".to_string()),
+        }
+    }
+
+    fn advance_return_route_id(&mut self) -> u32 {
+        let return_route_id = self.next_return_route_id;
+        self.next_return_route_id = return_route_id.wrapping_add(1);
+        return_route_id
+    }
+
+    fn route_index(&self) -> Arc<RouteIndex> {
+        let mut route_index_opt = self.route_index_opt.borrow_mut();
+        match route_index_opt.as_ref() {
+            Some(index) if index.generation() == self.neighborhood_database.generation() => {
+                index.clone()
+            }
+            _ => {
+                let index = Arc::new(RouteIndex::new(&self.neighborhood_database));
+                *route_index_opt = Some(index.clone());
+                index
+            }
+        }
+    }
+
+    // Main routing engine. Supply origin key as single-element vector in prefix, target key, if
+    // any, in target, and minimum hop count in hops_remaining. Return value is a list of the node
+    // sequences that will either go from the origin to the target in hops_remaining or more hops
+    // (but no more than a couple longer than the shortest way there) with no cycles, or from the
+    // origin hops_remaining hops out into the MASQ Network. No round trips; if you want a round
+    // trip, call this method twice. If the return value is empty, no qualifying route was found.
+    fn complete_routes<'a>(
+        &'a self,
+        prefix: Vec<&'a PublicKey>,
+        target_opt: Option<&'a PublicKey>,
+        hops_remaining: usize,
+        direction: RouteDirection,
+    ) -> Vec<Vec<&'a PublicKey>> {
+        let index = self.route_index();
+        let target_position_opt = match target_opt {
+            Some(target) => match index.position(target) {
+                Some(position) => Some(position),
+                None => return vec![],
+            },
+            None => None,
+        };
+        let query = RouteQuery {
+            prefix: prefix
+                .iter()
+                .map(|key| {
+                    index
+                        .position(key)
+                        .expect("Last Node magically disappeared")
+                })
+                .collect(),
+            target_opt: target_position_opt,
+            hops_remaining,
+            target_always_enterable: direction == RouteDirection::Back,
+        };
+        RouteIndex::search(&index, query, self.route_search_threads)
+            .into_iter()
+            .map(|route| {
+                route
+                    .into_iter()
+                    .map(|position| {
+                        self.neighborhood_database
+                            .node_by_key(index.key(position))
+                            .expect("Node magically disappeared")
+                            .public_key()
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn handle_gossip_reply(
+        &self,
+        gossip: Gossip_0v1,
+        target_key: &PublicKey,
+        target_node_addr: &NodeAddr,
+    ) {
+        self.send_no_lookup_package(
+            MessageType::Gossip(gossip.clone().into()),
+            target_key,
+            target_node_addr,
+        );
+        metrics::GOSSIP_SENT.add(1);
+        trace!(
+            self.logger,
+            "Sent Gossip: {}",
+            gossip.to_dot_graph(
+                self.neighborhood_database.root(),
+                (target_key, &Some(target_node_addr.clone())),
+            )
+        );
+    }
+
+    fn handle_gossip_failed(
+        &self,
+        gossip_failure: GossipFailure_0v1,
+        target_key: &PublicKey,
+        target_node_addr: &NodeAddr,
+    ) {
+        self.send_no_lookup_package(
+            MessageType::GossipFailure(VersionedData::new(
+                &crate::sub_lib::migrations::gossip_failure::MIGRATIONS,
+                &gossip_failure,
+            )),
+            target_key,
+            target_node_addr,
+        );
+        trace!(self.logger, "Sent GossipFailure_0v1: {}", gossip_failure);
+    }
+
+    fn handle_gossip_ignored(&self, _ignored_node_name: String, _gossip_record_count: usize) {
+        // Maybe something here eventually for keeping statistics
+    }
+
+    fn send_no_lookup_package(
+        &self,
+        message_type: MessageType,
+        target_key: &PublicKey,
+        target_node_addr: &NodeAddr,
+    ) {
+        let package = match NoLookupIncipientCoresPackage::new(
+            self.cryptde,
+            target_key,
+            target_node_addr,
+            message_type,
+        ) {
+            Ok(p) => p,
+            Err(e) => {
+                error!(self.logger, "{}", e);
+                return;
+            }
+        };
+        self.hopper_no_lookup
+            .as_ref()
+            .expect("No-lookup Hopper is unbound")
+            .try_send(package)
+            .expect("Hopper is dead");
+    }
+
+    fn gossip_source_name(
+        &self,
+        accessible_gossip: &[AccessibleGossipRecord],
+        gossip_source: SocketAddr,
+    ) -> String {
+        match accessible_gossip.iter().find(|agr| {
+            if let Some(ref node_addr) = agr.node_addr_opt {
+                node_addr.ip_addr() == gossip_source.ip()
+            } else {
+                false
+            }
+        }) {
+            Some(agr) => format!("{}", agr.inner.public_key),
+            None => format!("{}", gossip_source),
+        }
+    }
+
+    fn handle_stream_shutdown_msg(&mut self, msg: StreamShutdownMsg) {
+        if msg.stream_type != RemovedStreamType::Clandestine {
+            panic!("Neighborhood should never get ShutdownStreamMsg about non-clandestine stream")
+        }
+        let neighbor_key = match self.neighborhood_database.node_by_ip(&msg.peer_addr.ip()) {
+            None => {
+                warning!(self.logger, "Received shutdown notification for stream to {}, but no Node with that IP is in the database - ignoring", msg.peer_addr.ip());
+                return;
+            }
+            Some(n) => (n.public_key().clone()),
+        };
+        self.remove_neighbor(&neighbor_key, &msg.peer_addr);
+    }
+
+    fn remove_neighbor(&mut self, neighbor_key: &PublicKey, peer_addr: &SocketAddr) {
+        match self.neighborhood_database.remove_neighbor(neighbor_key) {
+            Err(e) => panic!("Node suddenly disappeared: {:?}", e),
+            Ok(true) => {
+                debug!(
+                    self.logger,
+                    "Received shutdown notification for {} at {}: removing neighborship",
+                    neighbor_key,
+                    peer_addr.ip()
+                );
+                self.gossip_to_neighbors()
+            }
+            Ok(false) => {
+                debug!(self.logger, "Received shutdown notification for {} at {}, but that Node is no neighbor - ignoring", neighbor_key, peer_addr.ip());
+            }
+        };
+    }
+
+    fn handle_neighborhood_graph_request(&self, client_id: u64, context_id: u64) {
+        let body = self.neighborhood_graph().tmb(context_id);
+        self.to_ui_message_sub
+            .as_ref()
+            .expect("UiGateway is unbound")
+            .try_send(NodeToUiMessage {
+                target: ClientId(client_id),
+                body,
+            })
+            .expect("UiGateway is dead");
+    }
+
+    fn handle_route_quote_request(
+        &self,
+        client_id: u64,
+        context_id: u64,
+        request: UiRouteQuoteRequest,
+    ) {
+        let hop_counts = request
+            .hop_counts_opt
+            .unwrap_or_else(|| (1..=DEFAULT_MINIMUM_HOP_COUNT).collect());
+        let body = if let Some(hop_count) = hop_counts
+            .iter()
+            .find(|hop_count| **hop_count > MAX_QUOTED_HOP_COUNT)
+        {
+            MessageBody {
+                opcode: UiRouteQuoteRequest::type_opcode().to_string(),
+                path: Conversation(context_id),
+                payload: Err((
+                    ROUTE_QUOTE_ERROR,
+                    format!(
+                        "Can't quote a {}-hop route: no more than {} hops, please",
+                        hop_count, MAX_QUOTED_HOP_COUNT
+                    ),
+                )),
+            }
+        } else {
+            let mut response = UiRouteQuoteResponse {
+                quotes: vec![],
+                unavailable_hop_counts: vec![],
+            };
+            hop_counts
+                .into_iter()
+                .for_each(|hop_count| match self.route_quote(hop_count) {
+                    Ok(quote) => response.quotes.push(quote),
+                    Err(e) => {
+                        debug!(self.logger, "No {}-hop route to quote: {}", hop_count, e);
+                        response.unavailable_hop_counts.push(hop_count)
+                    }
+                });
+            response.tmb(context_id)
+        };
+        self.to_ui_message_sub
+            .as_ref()
+            .expect("UiGateway is unbound")
+            .try_send(NodeToUiMessage {
+                target: ClientId(client_id),
+                body,
+            })
+            .expect("UiGateway is dead");
+    }
+
+    fn handle_connection_audit(&mut self, mut record: ConnectionAuditRecord) {
+        // Nodes that connect to us don't say who they are, but they've usually Gossiped by now
+        if record.public_key_opt.is_none() {
+            record.public_key_opt = self
+                .neighborhood_database
+                .node_by_ip(&record.peer_addr.ip())
+                .map(|node| node.public_key().clone());
+        }
+        let wallet_opt = record
+            .public_key_opt
+            .as_ref()
+            .and_then(|key| self.neighborhood_database.node_by_key(key))
+            .map(|node| node.earning_wallet());
+        let uptime_seconds = record.duration_ms / 1000;
+        if uptime_seconds > 0 {
+            self.add_trust_evidence(wallet_opt, TrustEvidence::Uptime(uptime_seconds));
+        }
+        match &self.connection_audit_dao_opt {
+            Some(dao) => dao.record(&record),
+            None => debug!(
+                self.logger,
+                "No database yet: can't audit {} connection with {}",
+                record.direction,
+                record.peer_addr
+            ),
+        }
+    }
+
+    fn handle_connection_audit_request(
+        &self,
+        client_id: u64,
+        context_id: u64,
+        request: UiConnectionAuditRequest,
+    ) {
+        let from = request.from_timestamp_opt.unwrap_or(0);
+        let to = request.to_timestamp_opt.unwrap_or(u64::MAX);
+        let result = if from > to {
+            Err(format!(
+                "fromTimestamp {} is later than toTimestamp {}",
+                from, to
+            ))
+        } else {
+            match &self.connection_audit_dao_opt {
+                Some(dao) => Ok(dao.connections(
+                    from,
+                    to,
+                    request.limit_opt.unwrap_or(DEFAULT_CONNECTION_AUDIT_LIMIT),
+                )),
+                None => Err("The connection audit log isn't open yet".to_string()),
+            }
+        };
+        let body = match result {
+            Ok(records) => UiConnectionAuditResponse {
+                connections: records
+                    .into_iter()
+                    .map(|record| UiAuditedConnection {
+                        public_key_opt: record.public_key_opt.map(|key| key.to_string()),
+                        peer_addr: record.peer_addr.to_string(),
+                        direction: record.direction.to_string(),
+                        opened_at: record.opened_at,
+                        duration_ms: record.duration_ms,
+                        bytes_in: record.bytes_in,
+                        bytes_out: record.bytes_out,
+                        disconnect_reason: record.disconnect_reason,
+                    })
+                    .collect(),
+            }
+            .tmb(context_id),
+            Err(e) => MessageBody {
+                opcode: UiConnectionAuditRequest::type_opcode().to_string(),
+                path: Conversation(context_id),
+                payload: Err((CONNECTION_AUDIT_ERROR, e)),
+            },
+        };
+        self.to_ui_message_sub
+            .as_ref()
+            .expect("UiGateway is unbound")
+            .try_send(NodeToUiMessage {
+                target: ClientId(client_id),
+                body,
+            })
+            .expect("UiGateway is dead");
+    }
+
+    fn handle_geo_database_request(&self, client_id: u64, context_id: u64) {
+        let body = match &self.geo_db_opt {
+            Some(geo_db) => {
+                let status = geo_db.status();
+                let now = SystemTime::now();
+                UiGeoDatabaseResponse {
+                    mirror_url: geo_db.mirror_url.clone(),
+                    age_seconds_opt: status.updated_at_opt.map(|updated_at| {
+                        now.duration_since(updated_at)
+                            .map(|age| age.as_secs())
+                            .unwrap_or(0)
+                    }),
+                    last_checked_opt: status.checked_at_opt.map(|checked_at| {
+                        checked_at
+                            .duration_since(UNIX_EPOCH)
+                            .expect("Clock is before the epoch")
+                            .as_secs()
+                    }),
+                    last_error_opt: status.error_opt,
+                    refreshing: status.refreshing,
+                }
+                .tmb(context_id)
+            }
+            None => no_geo_database(UiGeoDatabaseRequest::type_opcode(), context_id),
+        };
+        self.to_ui_message_sub
+            .as_ref()
+            .expect("UiGateway is unbound")
+            .try_send(NodeToUiMessage {
+                target: ClientId(client_id),
+                body,
+            })
+            .expect("UiGateway is dead");
+    }
+
+    fn handle_geo_database_refresh_request(&self, client_id: u64, context_id: u64) {
+        let body = match &self.geo_db_opt {
+            Some(geo_db) => {
+                info!(
+                    self.logger,
+                    "Refreshing the geolocation database from {} at client {}'s request",
+                    geo_db.mirror_url,
+                    client_id
+                );
+                geo_db.refresh();
+                UiGeoDatabaseRefreshResponse {}.tmb(context_id)
+            }
+            None => no_geo_database(UiGeoDatabaseRefreshRequest::type_opcode(), context_id),
+        };
+        self.to_ui_message_sub
+            .as_ref()
+            .expect("UiGateway is unbound")
+            .try_send(NodeToUiMessage {
+                target: ClientId(client_id),
+                body,
+            })
+            .expect("UiGateway is dead");
+    }
+
+    fn handle_speed_test_request(
+        &mut self,
+        client_id: u64,
+        context_id: u64,
+        request: UiSpeedTestRequest,
+        ctx: &mut Context<Neighborhood>,
+    ) {
+        let hop_counts = request
+            .hop_counts_opt
+            .unwrap_or_else(|| (1..=DEFAULT_MINIMUM_HOP_COUNT).collect());
+        if let Some(hop_count) = hop_counts
+            .iter()
+            .find(|hop_count| **hop_count > MAX_QUOTED_HOP_COUNT)
+        {
+            self.to_ui_message_sub
+                .as_ref()
+                .expect("UiGateway is unbound")
+                .try_send(NodeToUiMessage {
+                    target: ClientId(client_id),
+                    body: MessageBody {
+                        opcode: UiSpeedTestRequest::type_opcode().to_string(),
+                        path: Conversation(context_id),
+                        payload: Err((
+                            SPEED_TEST_ERROR,
+                            format!(
+                                "Can't test a {}-hop route: no more than {} hops, please",
+                                hop_count, MAX_QUOTED_HOP_COUNT
+                            ),
+                        )),
+                    },
+                })
+                .expect("UiGateway is dead");
+            return;
+        }
+        let mut speed_test = SpeedTest::new(client_id, context_id);
+        hop_counts
+            .into_iter()
+            .for_each(|hop_count| match self.speed_test_route(hop_count) {
+                Ok((over, back)) => speed_test
+                    .legs
+                    .push(SpeedTestLeg::new(hop_count, over, back)),
+                Err(e) => {
+                    debug!(self.logger, "No {}-hop route to test: {}", hop_count, e);
+                    speed_test.unavailable_hop_counts.push(hop_count)
+                }
+            });
+        let test_id = self.next_speed_test_id;
+        self.next_speed_test_id = self.next_speed_test_id.wrapping_add(1);
+        let leg_count = speed_test.legs.len();
+        self.speed_tests.insert(test_id, speed_test);
+        if leg_count == 0 {
+            return self.finish_speed_test(test_id);
+        }
+        (0..leg_count).for_each(|leg| self.send_speed_test_probe(test_id, leg, false));
+        ctx.run_later(SPEED_TEST_TIMEOUT, move |neighborhood, _ctx| {
+            neighborhood.finish_speed_test(test_id)
+        });
+    }
+
+    // A round trip of hop_count hops to the first exit, in key order, that answers probes
+    fn speed_test_route(&self, hop_count: usize) -> Result<(RouteSegment, RouteSegment), String> {
+        let root_key = self.cryptde.public_key();
+        let db = &self.neighborhood_database;
+        let exit_keys = db
+            .keys()
+            .into_iter()
+            .filter(|key| *key != root_key)
+            .filter(|key| {
+                db.node_by_key(key)
+                    .map(|node| node.features().contains(LINK_BENCHMARK_FEATURE))
+                    .unwrap_or(false)
+            })
+            .sorted()
+            .collect_vec();
+        let over = exit_keys
+            .into_iter()
+            .find_map(|exit_key| {
+                self.make_route_segment(
+                    root_key,
+                    Some(exit_key),
+                    hop_count,
+                    Component::Neighborhood,
+                    RouteDirection::Over,
+                    None,
+                    &[],
+                )
+                .ok()
+            })
+            .ok_or_else(|| "no exit on a route that long answers probes".to_string())?;
+        if over.keys.len() > 2 && self.consuming_wallet_opt.is_none() {
+            return Err("Cannot make multi-hop route segment without consuming wallet".to_string());
+        }
+        let exit_key = over.keys.last().expect("Empty segment").clone();
+        let back = self.make_route_segment(
+            &exit_key,
+            Some(root_key),
+            hop_count,
+            Component::Neighborhood,
+            RouteDirection::Back,
+            None,
+            &[],
+        )?;
+        Ok((over, back))
+    }
+
+    fn send_speed_test_probe(&mut self, test_id: u64, leg: usize, padded: bool) {
+        let (over, back) = match self
+            .speed_tests
+            .get(&test_id)
+            .and_then(|speed_test| speed_test.legs.get(leg))
+        {
+            Some(leg) => (leg.over.clone(), leg.back.clone()),
+            None => return,
+        };
+        let exit_key = over.keys.last().expect("Empty segment").clone();
+        let nonce = self.next_benchmark_nonce;
+        self.next_benchmark_nonce = self.next_benchmark_nonce.wrapping_add(1);
+        let return_route_id = self.advance_return_route_id();
+        let route = Route::round_trip(
+            over,
+            back,
+            self.cryptde,
+            self.consuming_wallet_opt.clone(),
+            return_route_id,
+            Some(contract_address(self.chain_id)),
+        )
+        .expect("Internal error: bad route");
+        let padding = if padded {
+            PlainData::from(vec![0u8; BENCHMARK_PROBE_BYTES])
+        } else {
+            PlainData::new(&[])
+        };
+        let probe = Benchmark_0v1::RouteProbe {
+            nonce,
+            originator: self.cryptde.public_key().clone(),
+            padding,
+        };
+        let package = IncipientCoresPackage::new(
+            self.cryptde,
+            route,
+            MessageType::Benchmark(probe.into()),
+            &exit_key,
+        )
+        .expect("Key magically disappeared");
+        self.speed_test_probes
+            .insert(nonce, (test_id, leg, padded, Instant::now()));
+        self.hopper
+            .as_ref()
+            .expect("unbound hopper")
+            .try_send(package)
+            .expect("hopper is dead");
+    }
+
+    fn finish_speed_test(&mut self, test_id: u64) {
+        let speed_test = match self.speed_tests.remove(&test_id) {
+            Some(speed_test) => speed_test,
+            None => return,
+        };
+        self.speed_test_probes
+            .retain(|_, (probe_test_id, _, _, _)| *probe_test_id != test_id);
+        self.to_ui_message_sub
+            .as_ref()
+            .expect("UiGateway is unbound")
+            .try_send(NodeToUiMessage {
+                target: ClientId(speed_test.client_id),
+                body: speed_test.response().tmb(speed_test.context_id),
+            })
+            .expect("UiGateway is dead");
+    }
+
+    // Prices the round trip make_round_trip_route would choose, without using up a return route ID
+    fn route_quote(&self, hop_count: usize) -> Result<UiRouteQuote, String> {
+        let root_key = self.cryptde.public_key();
+        let over = self.make_route_segment(
+            root_key,
+            None,
+            hop_count,
+            Component::ProxyClient,
+            RouteDirection::Over,
+            None,
+            &[],
+        )?;
+        let exit_key = over.keys.last().expect("Empty segment").clone();
+        let back = self.make_route_segment(
+            &exit_key,
+            Some(root_key),
+            hop_count,
+            Component::ProxyServer,
+            RouteDirection::Back,
+            None,
+            &[],
+        )?;
+        let over_services = self.make_expected_services(&over)?;
+        let back_services = self.make_expected_services(&back)?;
+        let rates = |services: &[ExpectedService]| -> (u64, u64) {
+            services
+                .iter()
+                .fold((0, 0), |(byte_rate, service_rate), service| match service {
+                    ExpectedService::Routing(_, _, rate_pack) => (
+                        byte_rate + rate_pack.routing_byte_rate,
+                        service_rate + rate_pack.routing_service_rate,
+                    ),
+                    ExpectedService::Exit(_, _, rate_pack) => (
+                        byte_rate + rate_pack.exit_byte_rate,
+                        service_rate + rate_pack.exit_service_rate,
+                    ),
+                    ExpectedService::Nothing => (byte_rate, service_rate),
+                })
+        };
+        let (request_byte_rate, request_service_rate) = rates(&over_services);
+        let (response_byte_rate, response_service_rate) = rates(&back_services);
+        Ok(UiRouteQuote {
+            hop_count,
+            exit_key: truncated_key(&exit_key),
+            request_cost_per_mb: request_byte_rate * BYTES_PER_MB,
+            response_cost_per_mb: response_byte_rate * BYTES_PER_MB,
+            service_cost: request_service_rate + response_service_rate,
+        })
+    }
+
+    fn handle_hop_timings(&mut self, msg: HopTimingsMessage) {
+        let hop_timings = match &self.timing_cryptde_opt {
+            Some(timing_cryptde) => match msg
+                .timings
+                .iter()
+                .map(|timing| decodex::<HopTiming>(timing_cryptde.as_ref(), timing))
+                .collect::<Result<Vec<HopTiming>, CodexError>>()
+            {
+                Ok(hop_timings) => hop_timings,
+                Err(e) => {
+                    warning!(
+                        self.logger,
+                        "Couldn't read {} hop timings: {:?}",
+                        msg.timings.len(),
+                        e
+                    );
+                    return;
+                }
+            },
+            None => {
+                debug!(
+                    self.logger,
+                    "Ignoring {} hop timings: --hop-timing is off",
+                    msg.timings.len()
+                );
+                return;
+            }
+        };
+        // Each Node held the response until the next one stamped it, and the last until it got here
+        let arrivals = hop_timings
+            .iter()
+            .skip(1)
+            .map(|hop_timing| hop_timing.sent_at_ms)
+            .chain(std::iter::once(msg.received_at_ms));
+        let latencies = hop_timings
+            .iter()
+            .zip(arrivals)
+            .map(|(hop_timing, arrival)| {
+                (
+                    hop_timing.public_key.clone(),
+                    arrival as i64 - hop_timing.sent_at_ms as i64,
+                )
+            })
+            .collect_vec();
+        debug!(
+            self.logger,
+            "Route back: {}",
+            render_route_latency(&latencies)
+        );
+        if self.route_latencies.len() >= MAX_ROUTE_LATENCIES {
+            self.route_latencies.pop_front();
+        }
+        self.route_latencies.push_back(latencies);
+    }
+
+    fn handle_collect_diagnostics_request(&self, client_id: u64, context_id: u64) {
+        let snapshot = graph_snapshot(&self.neighborhood_graph());
+        let (data_directory, chain_id, logger) = (
+            self.data_directory.clone(),
+            self.chain_id,
+            self.logger.clone(),
+        );
+        let to_ui_message_sub = self
+            .to_ui_message_sub
+            .as_ref()
+            .expect("UiGateway is unbound")
+            .clone();
+        // Counters such as refused replays only mean anything while the Node is running
+        let metrics_text = metrics::render(metrics::ALL_METRICS);
+        let route_latency_text_opt = self.timing_cryptde_opt.as_ref().map(|_| {
+            self.route_latencies
+                .iter()
+                .map(|latencies| render_route_latency(latencies) + "\n")
+                .collect::<String>()
+        });
+        // Empty unless --protocol-sniffing is on
+        let sniffed_streams = SNIFFED_STREAMS.samples();
+        // Compressing the logs can take a while, and the Neighborhood has better things to do
+        std::thread::spawn(move || {
+            let mut bundle = make_bundle(&data_directory, chain_id, Some(snapshot));
+            bundle.add_bytes("metrics.txt", metrics_text.into_bytes());
+            if let Some(route_latency_text) = route_latency_text_opt {
+                bundle.add_bytes("route_latency.txt", route_latency_text.into_bytes());
+            }
+            if !sniffed_streams.is_empty() {
+                bundle.add_bytes(
+                    "unrecognized_streams.json",
+                    samples_to_json(&sniffed_streams).into_bytes(),
+                );
+                bundle.add_bytes(
+                    "unrecognized_streams.pcap",
+                    samples_to_pcap(&sniffed_streams),
+                );
+            }
+            let body = match bundle.write(&data_directory) {
+                Ok(path) => {
+                    info!(
+                        logger,
+                        "UI {} collected diagnostics into {}",
+                        client_id,
+                        path.display()
+                    );
+                    UiCollectDiagnosticsResponse {
+                        path: path.to_string_lossy().to_string(),
+                    }
+                    .tmb(context_id)
+                }
+                Err(e) => {
+                    let message = format!(
+                        "Couldn't write diagnostics into {}: {}",
+                        data_directory.display(),
+                        e
+                    );
+                    warning!(logger, "{}", message);
+                    MessageBody {
+                        opcode: UiCollectDiagnosticsRequest::type_opcode().to_string(),
+                        path: Conversation(context_id),
+                        payload: Err((DIAGNOSTICS_ERROR, message)),
+                    }
+                }
+            };
+            to_ui_message_sub
+                .try_send(NodeToUiMessage {
+                    target: ClientId(client_id),
+                    body,
+                })
+                .expect("UiGateway is dead")
+        });
+    }
+
+    fn neighborhood_graph(&self) -> UiNeighborhoodGraphResponse {
+        let db = &self.neighborhood_database;
+        let root_key = db.root().public_key();
+        let records = db
+            .keys()
+            .into_iter()
+            .sorted()
+            .map(|key| db.node_by_key(key).expect("Node magically disappeared"))
+            .collect_vec();
+        let nodes = records
+            .iter()
+            .map(|node| UiNeighborhoodGraphNode {
+                public_key: truncated_key(node.public_key()),
+                is_local: node.public_key() == root_key,
+                version: node.version(),
+                accepts_connections: node.accepts_connections(),
+                routes_data: node.routes_data(),
+                rate_pack: UiRatePack {
+                    routing_byte_rate: node.rate_pack().routing_byte_rate,
+                    routing_service_rate: node.rate_pack().routing_service_rate,
+                    exit_byte_rate: node.rate_pack().exit_byte_rate,
+                    exit_service_rate: node.rate_pack().exit_service_rate,
+                },
+                country_code_opt: match (&self.geo_db_opt, node.node_addr_opt()) {
+                    (Some(geo_db), Some(node_addr)) => geo_db.country_code(node_addr.ip_addr()),
+                    _ => None,
+                },
+            })
+            .collect_vec();
+        let edges = records
+            .iter()
+            .flat_map(|node| {
+                node.inner
+                    .neighbors
+                    .iter()
+                    .map(move |neighbor_key| UiNeighborhoodGraphEdge {
+                        from: truncated_key(node.public_key()),
+                        to: truncated_key(neighbor_key),
+                    })
+            })
+            .collect_vec();
+        let renderables = nodes
+            .iter()
+            .cloned()
+            .map(|node| Box::new(node) as Box<dyn DotRenderable>)
+            .chain(
+                edges
+                    .iter()
+                    .cloned()
+                    .map(|edge| Box::new(edge) as Box<dyn DotRenderable>),
+            )
+            .collect_vec();
+        UiNeighborhoodGraphResponse {
+            nodes,
+            edges,
+            dot_graph: render_dot_graph(renderables),
+        }
+    }
+
+    #[allow(unreachable_code)]
+    fn handle_shutdown_order(
+        &mut self,
+        client_id: u64,
+        _msg: UiShutdownRequest,
+        ctx: &mut Context<Neighborhood>,
+    ) {
+        info!(
+            self.logger,
+            "Received shutdown order from client {}: shutting down hard", client_id
+        );
+        let message = format!(
+            "Received shutdown order from client {}: shutting down hard",
+            client_id
+        );
+        if self.say_farewell() {
+            ctx.run_later(FAREWELL_GOSSIP_GRACE, move |_, _| {
+                exit_process(0, &message);
+            });
+        } else {
+            exit_process(0, &message);
+        }
+    }
+
+    // Live Nodes' records never go long without a new version; see NODE_RECORD_TTL_SECS
+    fn refresh_root_record(&mut self) {
+        self.neighborhood_database.root_mut().increment_version();
+        self.gossip_to_neighbors();
+    }
+
+    // Tells the neighbors this Node is leaving, so the rest of the network can bury it now rather
+    // than waiting for its record to expire. Returns false if there was nobody to tell.
+    fn say_farewell(&mut self) -> bool {
+        if self.is_zero_hop
+            || self
+                .neighborhood_database
+                .root()
+                .half_neighbor_keys()
+                .is_empty()
+        {
+            return false;
+        }
+        let root = self.neighborhood_database.root_mut();
+        root.set_tombstone(true);
+        root.increment_version();
+        self.gossip_to_neighbors();
+        true
+    }
+}
+
+// Everything Gossip has to pass before the GossipAcceptor gets to see it. Gossip comes from
+// other Nodes, so no part of it can be trusted not to be malformed.
+pub fn accessible_gossip_records(
+    incoming_gossip: Gossip_0v1,
+    gossip_source: SocketAddr,
+    cryptde: &dyn CryptDE,
+    logger: &Logger,
+) -> Result<Vec<AccessibleGossipRecord>, String> {
+    let record_count = incoming_gossip.node_records.len();
+    let agrs: Vec<AccessibleGossipRecord> = incoming_gossip
+        .node_records
+        .into_iter()
+        .flat_map(AccessibleGossipRecord::try_from)
+        .collect();
+    if agrs.len() < record_count {
+        return Err(format!(
+            "Received non-deserializable Gossip from {}",
+            gossip_source
+        ));
+    }
+    let signature_invalid = |agr: &AccessibleGossipRecord| {
+        !cryptde.verify_signature(&agr.signed_gossip, &agr.signature, &agr.inner.public_key)
+    };
+    if agrs.iter().any(signature_invalid) {
+        return Err(format!(
+            "Received Gossip with invalid signature from {}",
+            gossip_source
+        ));
+    }
+    let node_addr_signature_invalid = |agr: &AccessibleGossipRecord| {
+        agr.node_addr_signature_status(cryptde) == NodeAddrSignatureStatus::Invalid
+    };
+    if agrs.iter().any(node_addr_signature_invalid) {
+        return Err(format!(
+            "Received Gossip with invalid NodeAddr signature from {}",
+            gossip_source
+        ));
+    }
+    Ok(agrs
+        .into_iter()
+        .map(|agr| strip_unsigned_node_addr(agr, gossip_source, cryptde, logger))
+        .collect())
+}
+
+// A NodeAddr that isn't signed by the Node it describes can only be trusted if it's the
+// address the Gossip actually came from; otherwise any Node could relocate any other Node.
+fn strip_unsigned_node_addr(
+    mut agr: AccessibleGossipRecord,
+    gossip_source: SocketAddr,
+    cryptde: &dyn CryptDE,
+    logger: &Logger,
+) -> AccessibleGossipRecord {
+    if agr.node_addr_signature_status(cryptde) != NodeAddrSignatureStatus::Unsigned {
+        return agr;
+    }
+    match &agr.node_addr_opt {
+        Some(node_addr) if node_addr.ip_addr() == gossip_source.ip() => agr,
+        Some(node_addr) => {
+            debug!(
+                logger,
+                "Discarding unsigned NodeAddr {} for {} in Gossip from {}",
+                node_addr,
+                agr.inner.public_key,
+                gossip_source
+            );
+            agr.node_addr_opt = None;
+            agr
+        }
+        None => agr,
+    }
+}
+
+fn no_geo_database(opcode: &str, context_id: u64) -> MessageBody {
+    MessageBody {
+        opcode: opcode.to_string(),
+        path: Conversation(context_id),
+        payload: Err((
+            GEO_DATABASE_ERROR,
+            "This Node keeps no geolocation database: start it with --geo-db-url".to_string(),
+        )),
+    }
+}
+
+fn truncated_key(public_key: &PublicKey) -> String {
+    public_key.to_string().chars().take(8).collect()
+}
+
+// Truncated keys and no addresses: the shape of the neighborhood, not who's in it
+fn graph_snapshot(graph: &UiNeighborhoodGraphResponse) -> String {
+    serde_json::to_string_pretty(&serde_json::json!({
+        "nodes": graph.nodes,
+        "edges": graph.edges,
+    }))
+    .expect("Couldn't serialize neighborhood snapshot")
+}
+
+fn render_route_latency(latencies: &[(PublicKey, i64)]) -> String {
+    let hops = latencies
+        .iter()
+        .map(|(public_key, millis)| format!("{} {}ms", truncated_key(public_key), millis))
+        .join(", ");
+    let total: i64 = latencies.iter().map(|(_, millis)| millis).sum();
+    format!("{}, total {}ms", hops, total)
+}
+
+pub fn regenerate_signed_gossip(
+    inner: &NodeRecordInner_0v1,
+    cryptde: &dyn CryptDE, // Must be the correct CryptDE for the Node from which inner came: used for signing
+) -> (PlainData, CryptData) {
+    let signed_gossip =
+        PlainData::from(serde_cbor::ser::to_vec(&inner).expect("Serialization failed"));
+    let signature = match cryptde.sign(&signed_gossip) {
+        Ok(sig) => sig,
+        Err(e) => unimplemented!("TODO: Signing error: {:?}", e),
+    };
+    (signed_gossip, signature)
+}
+
+pub fn sign_node_addr(
+    node_addr: &NodeAddr,
+    version: u32,
+    cryptde: &dyn CryptDE, // Must be the CryptDE of the Node at node_addr: used for signing
+) -> CryptData {
+    match cryptde.sign(&node_addr_signable_data(node_addr, version)) {
+        Ok(sig) => sig,
+        Err(e) => unimplemented!("TODO: Signing error: {:?}", e),
+    }
+}
+
+// The record's version is signed along with the NodeAddr, so that the signature on an address the
+// Node has since moved away from can't be replayed alongside a later version of its record
+fn node_addr_signable_data(node_addr: &NodeAddr, version: u32) -> PlainData {
+    PlainData::from(serde_cbor::ser::to_vec(&(version, node_addr)).expect("Serialization failed"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::blockchain_interface::{chain_id_from_name, contract_address};
+    use crate::db_config::persistent_configuration::PersistentConfigError;
+    use crate::geo_db::{tiny_country_database, GeoDbStatus, GEO_DB_FILENAME};
+    use crate::neighborhood::gossip::GossipBuilder;
+    use crate::neighborhood::gossip::Gossip_0v1;
+    use crate::neighborhood::gossip_recording::{read_recording, RecordingEntry};
+    use crate::neighborhood::node_record::{NodeRecord, NodeRecordInner_0v1};
+    use crate::stream_messages::{NonClandestineAttributes, RemovedStreamType};
+    use crate::sub_lib::cryptde::encodex;
+    use crate::sub_lib::descriptor_signature::sign_message;
+    use crate::sub_lib::dispatcher::Endpoint;
     use crate::sub_lib::hop::LiveHop;
     use crate::sub_lib::hopper::MessageType;
-    use crate::sub_lib::neighborhood::{ExpectedServices, NeighborhoodMode};
+    use crate::sub_lib::neighborhood::{ConnectionDirection, ExpectedServices, NeighborhoodMode};
     use crate::sub_lib::neighborhood::{NeighborhoodConfig, DEFAULT_RATE_PACK};
     use crate::sub_lib::peer_actors::PeerActors;
     use crate::sub_lib::stream_handler_pool::TransmitDataMsg;
@@ -1254,14 +3402,17 @@ mod tests {
     use crate::test_utils::recorder::Recorder;
     use crate::test_utils::recorder::Recording;
     use crate::test_utils::vec_to_set;
-    use crate::test_utils::{assert_contains, make_wallet};
-    use crate::test_utils::{main_cryptde, make_paying_wallet};
+    use crate::test_utils::{alias_cryptde, main_cryptde, make_paying_wallet};
+    use crate::test_utils::{assert_contains, make_wallet, wait_for};
+    use crate::trust_dao::TrustRecord;
     use actix::dev::{MessageResponse, ResponseChannel};
     use actix::Message;
     use actix::Recipient;
     use actix::System;
     use itertools::Itertools;
     use masq_lib::constants::TLS_PORT;
+    use masq_lib::exit_policy::ExitPolicy;
+    use masq_lib::messages::UiSpeedTestResponse;
     use masq_lib::test_utils::utils::{
         ensure_node_home_directory_exists, DEFAULT_CHAIN_ID, TEST_DEFAULT_CHAIN_NAME,
     };
@@ -1270,2932 +3421,5346 @@ mod tests {
     use masq_lib::utils::running_test;
     use serde_cbor;
     use std::cell::RefCell;
+    use std::collections::HashSet;
     use std::convert::TryInto;
+    use std::fs;
+    use std::io::Read;
     use std::net::{IpAddr, SocketAddr};
     use std::str::FromStr;
     use std::sync::{Arc, Mutex};
     use std::thread;
+    use std::time::SystemTime;
     use tokio::prelude::Future;
 
     #[test]
-    #[should_panic(expected = "Neighbor AQIDBA:1.2.3.4:1234 is not on the mainnet blockchain")]
-    fn cant_create_mainnet_neighborhood_with_non_mainnet_neighbors() {
-        let cryptde = main_cryptde();
-        let earning_wallet = make_wallet("earning");
-        let mut bc = bc_from_nc_plus(
-            NeighborhoodConfig {
-                mode: NeighborhoodMode::ConsumeOnly(vec![NodeDescriptor::from_str(
-                    cryptde,
-                    "AQIDBA:1.2.3.4:1234",
-                )
-                .unwrap()]),
-            },
-            earning_wallet.clone(),
-            None,
-            "cant_create_mainnet_neighborhood_with_non_mainnet_neighbors",
-        );
-        bc.blockchain_bridge_config.chain_id = chain_id_from_name(DEFAULT_CHAIN_NAME);
+    #[should_panic(expected = "Neighbor AQIDBA:1.2.3.4:1234 is not on the mainnet blockchain")]
+    fn cant_create_mainnet_neighborhood_with_non_mainnet_neighbors() {
+        let cryptde = main_cryptde();
+        let earning_wallet = make_wallet("earning");
+        let mut bc = bc_from_nc_plus(
+            NeighborhoodConfig {
+                mode: NeighborhoodMode::ConsumeOnly(vec![NodeDescriptor::from_str(
+                    cryptde,
+                    "AQIDBA:1.2.3.4:1234",
+                )
+                .unwrap()]),
+            },
+            earning_wallet.clone(),
+            None,
+            "cant_create_mainnet_neighborhood_with_non_mainnet_neighbors",
+        );
+        bc.blockchain_bridge_config.chain_id = chain_id_from_name(DEFAULT_CHAIN_NAME);
+
+        let _ = Neighborhood::new(cryptde, &bc);
+    }
+
+    #[test]
+    #[should_panic(expected = "Neighbor AQIDBA@1.2.3.4:1234 is on the mainnet blockchain")]
+    fn cant_create_non_mainnet_neighborhood_with_mainnet_neighbors() {
+        let cryptde = main_cryptde();
+        let earning_wallet = make_wallet("earning");
+        let mut bc = bc_from_nc_plus(
+            NeighborhoodConfig {
+                mode: NeighborhoodMode::ConsumeOnly(vec![NodeDescriptor::from_str(
+                    cryptde,
+                    "AQIDBA@1.2.3.4:1234",
+                )
+                .unwrap()]),
+            },
+            earning_wallet.clone(),
+            None,
+            "cant_create_non_mainnet_neighborhood_with_mainnet_neighbors",
+        );
+        bc.blockchain_bridge_config.chain_id = chain_id_from_name(TEST_DEFAULT_CHAIN_NAME);
+
+        let _ = Neighborhood::new(cryptde, &bc);
+    }
+
+    #[test]
+    fn node_with_zero_hop_config_creates_single_node_database() {
+        let cryptde = main_cryptde();
+        let earning_wallet = make_wallet("earning");
+
+        let subject = Neighborhood::new(
+            cryptde,
+            &bc_from_nc_plus(
+                NeighborhoodConfig {
+                    mode: NeighborhoodMode::ZeroHop,
+                },
+                earning_wallet.clone(),
+                None,
+                "node_with_zero_hop_config_creates_single_node_database",
+            ),
+        );
+
+        let root_node_record_ref = subject.neighborhood_database.root();
+
+        assert_eq!(root_node_record_ref.public_key(), cryptde.public_key());
+        assert_eq!(root_node_record_ref.node_addr_opt(), None);
+        assert_eq!(root_node_record_ref.half_neighbor_keys().len(), 0);
+    }
+
+    #[test]
+    fn routes_are_searched_for_on_the_configured_number_of_threads() {
+        let mut bc = bc_from_nc_plus(
+            NeighborhoodConfig {
+                mode: NeighborhoodMode::ZeroHop,
+            },
+            make_wallet("earning"),
+            None,
+            "routes_are_searched_for_on_the_configured_number_of_threads",
+        );
+        bc.route_search_threads = 7;
+
+        let subject = Neighborhood::new(main_cryptde(), &bc);
+
+        assert_eq!(subject.route_search_threads, 7);
+    }
+
+    #[test]
+    fn node_with_originate_only_config_is_decentralized_with_neighbor_but_not_ip() {
+        let cryptde: &dyn CryptDE = main_cryptde();
+        let neighbor: NodeRecord = make_node_record(1234, true);
+        let earning_wallet = make_wallet("earning");
+
+        let subject = Neighborhood::new(
+            cryptde,
+            &bc_from_nc_plus(
+                NeighborhoodConfig {
+                    mode: NeighborhoodMode::OriginateOnly(
+                        vec![neighbor.node_descriptor(DEFAULT_CHAIN_ID, cryptde)],
+                        DEFAULT_RATE_PACK.clone(),
+                    ),
+                },
+                earning_wallet.clone(),
+                None,
+                "node_with_originate_only_config_is_decentralized_with_neighbor_but_not_ip",
+            ),
+        );
+
+        let root_node_record_ref = subject.neighborhood_database.root();
+
+        assert_eq!(root_node_record_ref.public_key(), cryptde.public_key());
+        assert_eq!(root_node_record_ref.accepts_connections(), false);
+        assert_eq!(root_node_record_ref.routes_data(), true);
+        assert_eq!(root_node_record_ref.node_addr_opt(), None);
+        assert_eq!(root_node_record_ref.half_neighbor_keys().len(), 0);
+    }
+
+    #[test]
+    fn node_with_zero_hop_config_ignores_start_message() {
+        init_test_logging();
+        let cryptde = main_cryptde();
+        let earning_wallet = make_wallet("earning");
+        let consuming_wallet = Some(make_paying_wallet(b"consuming"));
+        let system =
+            System::new("node_with_no_neighbor_configs_ignores_bootstrap_neighborhood_now_message");
+        let subject = Neighborhood::new(
+            cryptde,
+            &bc_from_nc_plus(
+                NeighborhoodConfig {
+                    mode: NeighborhoodMode::ZeroHop,
+                },
+                earning_wallet.clone(),
+                consuming_wallet.clone(),
+                "node_with_zero_hop_config_ignores_start_message",
+            ),
+        );
+        let addr: Addr<Neighborhood> = subject.start();
+        let sub = addr.clone().recipient::<StartMessage>();
+        let (hopper, _, hopper_recording_arc) = make_recorder();
+        let peer_actors = peer_actors_builder().hopper(hopper).build();
+        addr.try_send(BindMessage { peer_actors }).unwrap();
+
+        sub.try_send(StartMessage {}).unwrap();
+
+        System::current().stop_with_code(0);
+        system.run();
+        let recording = hopper_recording_arc.lock().unwrap();
+        assert_eq!(recording.len(), 0);
+        TestLogHandler::new()
+            .exists_log_containing("INFO: Neighborhood: Empty. No Nodes to report to; continuing");
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "--neighbors node descriptors must have IP address and port list, not 'AwQFBg::'"
+    )]
+    fn node_with_neighbor_config_having_no_node_addr_panics() {
+        let cryptde: &dyn CryptDE = main_cryptde();
+        let earning_wallet = make_wallet("earning");
+        let consuming_wallet = Some(make_paying_wallet(b"consuming"));
+        let neighbor_node = make_node_record(3456, true);
+        let system = System::new("node_with_bad_neighbor_config_panics");
+        let subject = Neighborhood::new(
+            cryptde,
+            &bc_from_nc_plus(
+                NeighborhoodConfig {
+                    mode: NeighborhoodMode::Standard(
+                        NodeAddr::new(&IpAddr::from_str("5.4.3.2").unwrap(), &[5678]),
+                        vec![NodeDescriptor::from((
+                            neighbor_node.public_key(),
+                            DEFAULT_CHAIN_ID == chain_id_from_name(DEFAULT_CHAIN_NAME),
+                            cryptde,
+                        ))],
+                        rate_pack(100),
+                    ),
+                },
+                earning_wallet.clone(),
+                consuming_wallet.clone(),
+                "node_with_neighbor_config_having_no_node_addr_panics",
+            ),
+        );
+        let addr: Addr<Neighborhood> = subject.start();
+        let sub = addr.clone().recipient::<StartMessage>();
+        let peer_actors = peer_actors_builder().build();
+        addr.try_send(BindMessage { peer_actors }).unwrap();
+
+        sub.try_send(StartMessage {}).unwrap();
+
+        System::current().stop_with_code(0);
+        system.run();
+    }
+
+    #[test]
+    fn neighborhood_adds_nodes_and_links() {
+        let cryptde: &dyn CryptDE = main_cryptde();
+        let earning_wallet = make_wallet("earning");
+        let consuming_wallet = Some(make_paying_wallet(b"consuming"));
+        let one_neighbor_node = make_node_record(3456, true);
+        let another_neighbor_node = make_node_record(4567, true);
+        let this_node_addr = NodeAddr::new(&IpAddr::from_str("5.4.3.2").unwrap(), &[5678]);
+
+        let subject = Neighborhood::new(
+            cryptde,
+            &bc_from_nc_plus(
+                NeighborhoodConfig {
+                    mode: NeighborhoodMode::Standard(
+                        this_node_addr.clone(),
+                        vec![
+                            NodeDescriptor::from((
+                                &one_neighbor_node,
+                                DEFAULT_CHAIN_ID == chain_id_from_name(DEFAULT_CHAIN_NAME),
+                                cryptde,
+                            )),
+                            NodeDescriptor::from((
+                                &another_neighbor_node,
+                                DEFAULT_CHAIN_ID == chain_id_from_name(DEFAULT_CHAIN_NAME),
+                                cryptde,
+                            )),
+                        ],
+                        rate_pack(100),
+                    ),
+                },
+                earning_wallet.clone(),
+                consuming_wallet.clone(),
+                "neighborhood_adds_nodes_and_links",
+            ),
+        );
+
+        let root_node_record_ref = subject.neighborhood_database.root();
+
+        assert_eq!(
+            root_node_record_ref.node_addr_opt().unwrap().clone(),
+            this_node_addr
+        );
+
+        assert_eq!(
+            root_node_record_ref.has_half_neighbor(one_neighbor_node.public_key()),
+            false,
+        );
+        assert_eq!(
+            root_node_record_ref.has_half_neighbor(another_neighbor_node.public_key()),
+            false,
+        );
+        assert_eq!(
+            subject.initial_neighbors,
+            vec![
+                NodeDescriptor::from((
+                    &one_neighbor_node,
+                    DEFAULT_CHAIN_ID == chain_id_from_name(DEFAULT_CHAIN_NAME),
+                    cryptde,
+                )),
+                NodeDescriptor::from((
+                    &another_neighbor_node,
+                    DEFAULT_CHAIN_ID == chain_id_from_name(DEFAULT_CHAIN_NAME),
+                    cryptde,
+                ))
+            ]
+        );
+    }
+
+    #[test]
+    fn gossip_failures_eventually_stop_the_neighborhood() {
+        init_test_logging();
+        let cryptde: &dyn CryptDE = main_cryptde();
+        let earning_wallet = make_wallet("earning");
+        let one_neighbor_node: NodeRecord = make_node_record(3456, true);
+        let another_neighbor_node: NodeRecord = make_node_record(4567, true);
+        let this_node_addr = NodeAddr::new(&IpAddr::from_str("5.4.3.2").unwrap(), &[5678]);
+
+        let subject = Neighborhood::new(
+            cryptde,
+            &bc_from_nc_plus(
+                NeighborhoodConfig {
+                    mode: NeighborhoodMode::Standard(
+                        this_node_addr.clone(),
+                        vec![
+                            NodeDescriptor::from((
+                                &one_neighbor_node,
+                                DEFAULT_CHAIN_ID == chain_id_from_name(DEFAULT_CHAIN_NAME),
+                                cryptde,
+                            )),
+                            NodeDescriptor::from((
+                                &another_neighbor_node,
+                                DEFAULT_CHAIN_ID == chain_id_from_name(DEFAULT_CHAIN_NAME),
+                                cryptde,
+                            )),
+                        ],
+                        rate_pack(100),
+                    ),
+                },
+                earning_wallet.clone(),
+                None,
+                "gossip_failures_eventually_stop_the_neighborhood",
+            ),
+        );
+        let ecp1 = ExpiredCoresPackage::new(
+            one_neighbor_node.node_addr_opt().unwrap().into(),
+            None,
+            make_meaningless_route(),
+            GossipFailure_0v1::NoNeighbors,
+            0,
+        );
+        let ecp2 = ExpiredCoresPackage::new(
+            another_neighbor_node.node_addr_opt().unwrap().into(),
+            None,
+            make_meaningless_route(),
+            GossipFailure_0v1::ManualRejection,
+            0,
+        );
+        let system = System::new("responds_with_none_when_initially_configured_with_no_data");
+        let addr: Addr<Neighborhood> = subject.start();
+        let sub = addr.recipient::<ExpiredCoresPackage<GossipFailure_0v1>>();
+
+        sub.try_send(ecp1).unwrap();
+        sub.try_send(ecp2).unwrap();
+
+        system.run(); // If this never halts, it's because the Neighborhood isn't properly killing its actor
+
+        let tlh = TestLogHandler::new();
+        tlh.exists_log_containing ("WARN: Neighborhood: Node at 3.4.5.6 refused Debut: No neighbors for Introduction or Pass");
+        tlh.exists_log_containing ("WARN: Neighborhood: Node at 4.5.6.7 refused Debut: Node owner manually rejected your Debut");
+        tlh.exists_log_containing ("ERROR: Neighborhood: None of the Nodes listed in the --neighbors parameter could accept your Debut; shutting down");
+    }
+
+    #[test]
+    fn node_query_responds_with_none_when_initially_configured_with_no_data() {
+        let system = System::new("responds_with_none_when_initially_configured_with_no_data");
+        let subject = make_standard_subject();
+        let addr: Addr<Neighborhood> = subject.start();
+        let sub: Recipient<NodeQueryMessage> = addr.recipient::<NodeQueryMessage>();
+
+        let future = sub.send(NodeQueryMessage::PublicKey(PublicKey::new(&b"booga"[..])));
+
+        System::current().stop_with_code(0);
+        system.run();
+        let result = future.wait().unwrap();
+        assert_eq!(result.is_none(), true);
+    }
+
+    #[test]
+    fn node_query_responds_with_none_when_key_query_matches_no_configured_data() {
+        let cryptde: &dyn CryptDE = main_cryptde();
+        let earning_wallet = make_wallet("earning");
+        let consuming_wallet = Some(make_paying_wallet(b"consuming"));
+        let system =
+            System::new("node_query_responds_with_none_when_key_query_matches_no_configured_data");
+        let subject = Neighborhood::new(
+            cryptde,
+            &bc_from_nc_plus(
+                NeighborhoodConfig {
+                    mode: NeighborhoodMode::Standard(
+                        NodeAddr::new(&IpAddr::from_str("5.4.3.2").unwrap(), &[5678]),
+                        vec![NodeDescriptor::from((
+                            &PublicKey::new(&b"booga"[..]),
+                            &NodeAddr::new(&IpAddr::from_str("1.2.3.4").unwrap(), &[1234, 2345]),
+                            DEFAULT_CHAIN_ID == chain_id_from_name(DEFAULT_CHAIN_NAME),
+                            cryptde,
+                        ))],
+                        rate_pack(100),
+                    ),
+                },
+                earning_wallet.clone(),
+                consuming_wallet.clone(),
+                "node_query_responds_with_none_when_key_query_matches_no_configured_data",
+            ),
+        );
+        let addr: Addr<Neighborhood> = subject.start();
+        let sub: Recipient<NodeQueryMessage> = addr.recipient::<NodeQueryMessage>();
+
+        let future = sub.send(NodeQueryMessage::PublicKey(PublicKey::new(&b"blah"[..])));
+
+        System::current().stop_with_code(0);
+        system.run();
+        let result = future.wait().unwrap();
+        assert_eq!(result.is_none(), true);
+    }
+
+    #[test]
+    fn node_query_responds_with_result_when_key_query_matches_configured_data() {
+        let cryptde = main_cryptde();
+        let earning_wallet = make_wallet("earning");
+        let consuming_wallet = Some(make_paying_wallet(b"consuming"));
+        let system =
+            System::new("node_query_responds_with_result_when_key_query_matches_configured_data");
+        let one_neighbor = make_node_record(2345, true);
+        let another_neighbor = make_node_record(3456, true);
+        let mut subject = Neighborhood::new(
+            cryptde,
+            &bc_from_nc_plus(
+                NeighborhoodConfig {
+                    mode: NeighborhoodMode::Standard(
+                        NodeAddr::new(&IpAddr::from_str("5.4.3.2").unwrap(), &[5678]),
+                        vec![node_record_to_neighbor_config(&one_neighbor)],
+                        rate_pack(100),
+                    ),
+                },
+                earning_wallet.clone(),
+                consuming_wallet.clone(),
+                "node_query_responds_with_result_when_key_query_matches_configured_data",
+            ),
+        );
+        subject
+            .neighborhood_database
+            .add_node(another_neighbor.clone())
+            .unwrap();
+        let addr: Addr<Neighborhood> = subject.start();
+        let sub: Recipient<NodeQueryMessage> = addr.recipient::<NodeQueryMessage>();
+
+        let future = sub.send(NodeQueryMessage::PublicKey(
+            another_neighbor.public_key().clone(),
+        ));
+
+        System::current().stop_with_code(0);
+        system.run();
+        let result = future.wait().unwrap();
+        assert_eq!(
+            result.unwrap(),
+            NodeQueryResponseMetadata::new(
+                another_neighbor.public_key().clone(),
+                Some(another_neighbor.node_addr_opt().unwrap().clone()),
+                another_neighbor.rate_pack().clone(),
+            )
+        );
+    }
+
+    #[test]
+    fn node_query_responds_with_none_when_ip_address_query_matches_no_configured_data() {
+        let cryptde: &dyn CryptDE = main_cryptde();
+        let earning_wallet = make_wallet("earning");
+        let consuming_wallet = Some(make_paying_wallet(b"consuming"));
+        let system = System::new(
+            "node_query_responds_with_none_when_ip_address_query_matches_no_configured_data",
+        );
+        let subject = Neighborhood::new(
+            cryptde,
+            &bc_from_nc_plus(
+                NeighborhoodConfig {
+                    mode: NeighborhoodMode::Standard(
+                        NodeAddr::new(&IpAddr::from_str("5.4.3.2").unwrap(), &[5678]),
+                        vec![NodeDescriptor::from((
+                            &PublicKey::new(&b"booga"[..]),
+                            &NodeAddr::new(&IpAddr::from_str("1.2.3.4").unwrap(), &[1234, 2345]),
+                            DEFAULT_CHAIN_ID == chain_id_from_name(DEFAULT_CHAIN_NAME),
+                            cryptde,
+                        ))],
+                        rate_pack(100),
+                    ),
+                },
+                earning_wallet.clone(),
+                consuming_wallet.clone(),
+                "node_query_responds_with_none_when_ip_address_query_matches_no_configured_data",
+            ),
+        );
+        let addr: Addr<Neighborhood> = subject.start();
+        let sub: Recipient<NodeQueryMessage> = addr.recipient::<NodeQueryMessage>();
+
+        let future = sub.send(NodeQueryMessage::IpAddress(
+            IpAddr::from_str("2.3.4.5").unwrap(),
+        ));
+
+        System::current().stop_with_code(0);
+        system.run();
+        let result = future.wait().unwrap();
+        assert_eq!(result.is_none(), true);
+    }
+
+    #[test]
+    fn node_query_responds_with_result_when_ip_address_query_matches_configured_data() {
+        let cryptde: &dyn CryptDE = main_cryptde();
+        let system = System::new(
+            "node_query_responds_with_result_when_ip_address_query_matches_configured_data",
+        );
+        let node_record = make_node_record(1234, true);
+        let another_node_record = make_node_record(2345, true);
+        let mut subject = Neighborhood::new(
+            cryptde,
+            &bc_from_nc_plus(
+                NeighborhoodConfig {
+                    mode: NeighborhoodMode::Standard(
+                        node_record.node_addr_opt().unwrap(),
+                        vec![NodeDescriptor::from((
+                            &node_record,
+                            DEFAULT_CHAIN_ID == chain_id_from_name(DEFAULT_CHAIN_NAME),
+                            cryptde,
+                        ))],
+                        rate_pack(100),
+                    ),
+                },
+                node_record.earning_wallet(),
+                None,
+                "node_query_responds_with_result_when_ip_address_query_matches_configured_data",
+            ),
+        );
+        subject
+            .neighborhood_database
+            .add_node(another_node_record.clone())
+            .unwrap();
+        let addr: Addr<Neighborhood> = subject.start();
+        let sub: Recipient<NodeQueryMessage> = addr.recipient::<NodeQueryMessage>();
+
+        let future = sub.send(NodeQueryMessage::IpAddress(
+            IpAddr::from_str("2.3.4.5").unwrap(),
+        ));
+
+        System::current().stop_with_code(0);
+        system.run();
+        let result = future.wait().unwrap();
+        assert_eq!(
+            result.unwrap(),
+            NodeQueryResponseMetadata::new(
+                another_node_record.public_key().clone(),
+                Some(another_node_record.node_addr_opt().unwrap().clone()),
+                another_node_record.rate_pack().clone(),
+            )
+        );
+    }
+
+    #[test]
+    fn route_query_responds_with_none_when_asked_for_route_with_too_many_hops() {
+        let system =
+            System::new("route_query_responds_with_none_when_asked_for_route_with_too_many_hops");
+        let subject = make_standard_subject();
+        let addr: Addr<Neighborhood> = subject.start();
+        let sub: Recipient<RouteQueryMessage> = addr.recipient::<RouteQueryMessage>();
+
+        let future = sub.send(RouteQueryMessage::data_indefinite_route_request(5));
+
+        System::current().stop_with_code(0);
+        system.run();
+        let result = future.wait().unwrap();
+        assert_eq!(result, None);
+    }
+
+    #[test]
+    fn route_query_responds_with_none_when_asked_for_two_hop_round_trip_route_without_consuming_wallet(
+    ) {
+        let system = System::new("route_query_responds_with_none_when_asked_for_two_hop_round_trip_route_without_consuming_wallet");
+        let subject = make_standard_subject();
+        let addr: Addr<Neighborhood> = subject.start();
+        let sub: Recipient<RouteQueryMessage> = addr.recipient::<RouteQueryMessage>();
+
+        let future = sub.send(RouteQueryMessage::data_indefinite_route_request(2));
+
+        System::current().stop_with_code(0);
+        system.run();
+        let result = future.wait().unwrap();
+        assert_eq!(result, None);
+    }
+
+    #[test]
+    fn route_query_succeeds_when_asked_for_one_hop_round_trip_route_without_consuming_wallet() {
+        let cryptde = main_cryptde();
+        let earning_wallet = make_wallet("earning");
+        let system = System::new(
+            "route_query_succeeds_when_asked_for_one_hop_round_trip_route_without_consuming_wallet",
+        );
+        let mut subject = make_standard_subject();
+        subject
+            .neighborhood_database
+            .root_mut()
+            .set_earning_wallet(earning_wallet);
+        subject.consuming_wallet_opt = None;
+        // These happen to be extracted in the desired order. We could not think of a way to guarantee it.
+        let mut undesirable_exit_node = make_node_record(2345, true);
+        let desirable_exit_node = make_node_record(3456, false);
+        undesirable_exit_node.set_desirable(false);
+        let originating_node = &subject.neighborhood_database.root().clone();
+        {
+            let db = &mut subject.neighborhood_database;
+            db.add_node(undesirable_exit_node.clone()).unwrap();
+            db.add_node(desirable_exit_node.clone()).unwrap();
+            db.add_arbitrary_full_neighbor(
+                undesirable_exit_node.public_key(),
+                originating_node.public_key(),
+            );
+            db.add_arbitrary_full_neighbor(
+                desirable_exit_node.public_key(),
+                originating_node.public_key(),
+            );
+        }
+        let addr: Addr<Neighborhood> = subject.start();
+        let sub: Recipient<RouteQueryMessage> = addr.recipient::<RouteQueryMessage>();
+        let msg = RouteQueryMessage::data_indefinite_route_request(1);
+
+        let future = sub.send(msg);
+
+        System::current().stop_with_code(0);
+        system.run();
+        let segment = |nodes: Vec<&NodeRecord>, component: Component| {
+            RouteSegment::new(
+                nodes.into_iter().map(|n| n.public_key()).collect(),
+                component,
+            )
+        };
+        let result = future.wait().unwrap().unwrap();
+        let expected_response = RouteQueryResponse {
+            route: Route::round_trip(
+                segment(
+                    vec![originating_node, &desirable_exit_node],
+                    Component::ProxyClient,
+                ),
+                segment(
+                    vec![&desirable_exit_node, originating_node],
+                    Component::ProxyServer,
+                ),
+                cryptde,
+                None,
+                0,
+                None,
+            )
+            .unwrap(),
+            expected_services: ExpectedServices::RoundTrip(
+                vec![
+                    ExpectedService::Nothing,
+                    ExpectedService::Exit(
+                        desirable_exit_node.public_key().clone(),
+                        desirable_exit_node.earning_wallet(),
+                        rate_pack(3456),
+                    ),
+                ],
+                vec![
+                    ExpectedService::Exit(
+                        desirable_exit_node.public_key().clone(),
+                        desirable_exit_node.earning_wallet(),
+                        rate_pack(3456),
+                    ),
+                    ExpectedService::Nothing,
+                ],
+                0,
+            ),
+        };
+        assert_eq!(expected_response, result);
+    }
+
+    #[test]
+    fn route_query_responds_with_none_when_asked_for_one_hop_round_trip_route_without_consuming_wallet_when_back_route_needs_two_hops(
+    ) {
+        let system = System::new("route_query_responds_with_none_when_asked_for_one_hop_round_trip_route_without_consuming_wallet_when_back_route_needs_two_hops");
+        let mut subject = make_standard_subject();
+        let a = &make_node_record(1234, true);
+        let b = &subject.neighborhood_database.root().clone();
+        let c = &make_node_record(3456, true);
+        {
+            let db = &mut subject.neighborhood_database;
+            db.add_node(a.clone()).unwrap();
+            db.add_node(c.clone()).unwrap();
+            let mut single_edge = |a: &NodeRecord, b: &NodeRecord| {
+                db.add_arbitrary_half_neighbor(a.public_key(), b.public_key())
+            };
+            single_edge(a, b);
+            single_edge(b, c);
+            single_edge(c, a);
+        }
+        let addr: Addr<Neighborhood> = subject.start();
+        let sub: Recipient<RouteQueryMessage> = addr.recipient::<RouteQueryMessage>();
+        let msg = RouteQueryMessage::data_indefinite_route_request(1);
+
+        let future = sub.send(msg);
+
+        System::current().stop_with_code(0);
+        system.run();
+        let result = future.wait().unwrap();
+        assert_eq!(result, None);
+    }
+
+    #[test]
+    fn route_query_responds_with_none_when_asked_for_two_hop_one_way_route_without_consuming_wallet(
+    ) {
+        let system = System::new("route_query_responds_with_none_when_asked_for_two_hop_one_way_route_without_consuming_wallet");
+        let subject = make_standard_subject();
+        let addr: Addr<Neighborhood> = subject.start();
+        let sub: Recipient<RouteQueryMessage> = addr.recipient::<RouteQueryMessage>();
+        let msg = RouteQueryMessage::data_indefinite_route_request(2);
+
+        let future = sub.send(msg);
+
+        System::current().stop_with_code(0);
+        system.run();
+        let result = future.wait().unwrap();
+        assert_eq!(result, None);
+    }
+
+    #[test]
+    fn route_query_responds_with_standard_zero_hop_route_when_requested() {
+        let cryptde = main_cryptde();
+        let system = System::new("responds_with_standard_zero_hop_route_when_requested");
+        let subject = make_standard_subject();
+        let addr: Addr<Neighborhood> = subject.start();
+        let sub: Recipient<RouteQueryMessage> = addr.recipient::<RouteQueryMessage>();
+
+        let future = sub.send(RouteQueryMessage::data_indefinite_route_request(0));
+
+        System::current().stop_with_code(0);
+        system.run();
+        let result = future.wait().unwrap().unwrap();
+        let expected_response = RouteQueryResponse {
+            route: Route::round_trip(
+                RouteSegment::new(
+                    vec![&cryptde.public_key(), &cryptde.public_key()],
+                    Component::ProxyClient,
+                ),
+                RouteSegment::new(
+                    vec![&cryptde.public_key(), &cryptde.public_key()],
+                    Component::ProxyServer,
+                ),
+                cryptde,
+                None,
+                0,
+                None,
+            )
+            .unwrap(),
+            expected_services: ExpectedServices::RoundTrip(
+                vec![ExpectedService::Nothing, ExpectedService::Nothing],
+                vec![ExpectedService::Nothing, ExpectedService::Nothing],
+                0,
+            ),
+        };
+        assert_eq!(result, expected_response);
+    }
+
+    #[test]
+    fn zero_hop_routing_handles_return_route_id_properly() {
+        let mut subject = make_standard_subject();
+        let result0 = subject.zero_hop_route_response();
+        let result1 = subject.zero_hop_route_response();
+
+        let return_route_id_0 = match result0.expected_services {
+            ExpectedServices::RoundTrip(_, _, id) => id,
+            _ => panic!("expected RoundTrip got OneWay"),
+        };
+
+        let return_route_id_1 = match result1.expected_services {
+            ExpectedServices::RoundTrip(_, _, id) => id,
+            _ => panic!("expected RoundTrip got OneWay"),
+        };
+
+        assert_eq!(return_route_id_0, 0);
+        assert_eq!(return_route_id_1, 1);
+    }
+
+    /*
+            Database:
+
+                 P---Q---R---S
+                     |
+                     T
+
+            Tests will be written from the viewpoint of P.
+    */
+
+    #[test]
+    fn route_query_messages() {
+        let cryptde = main_cryptde();
+        let earning_wallet = make_wallet("earning");
+        let system = System::new("route_query_messages");
+        let mut subject = make_standard_subject();
+        subject
+            .neighborhood_database
+            .root_mut()
+            .set_earning_wallet(earning_wallet);
+        let consuming_wallet_opt = subject.consuming_wallet_opt.clone();
+        let p = &subject.neighborhood_database.root().clone();
+        let q = &make_node_record(3456, true);
+        let r = &make_node_record(4567, false);
+        let s = &make_node_record(5678, false);
+        let mut t = make_node_record(1111, false);
+        t.set_desirable(false);
+        {
+            let db = &mut subject.neighborhood_database;
+            db.add_node(q.clone()).unwrap();
+            db.add_node(t.clone()).unwrap();
+            db.add_node(r.clone()).unwrap();
+            db.add_node(s.clone()).unwrap();
+            let mut dual_edge = |a: &NodeRecord, b: &NodeRecord| {
+                db.add_arbitrary_full_neighbor(a.public_key(), b.public_key());
+            };
+            dual_edge(p, q);
+            dual_edge(q, &t);
+            dual_edge(q, r);
+            dual_edge(r, s);
+        }
+
+        let addr: Addr<Neighborhood> = subject.start();
+        let sub: Recipient<RouteQueryMessage> = addr.recipient::<RouteQueryMessage>();
+
+        let data_route = sub.send(RouteQueryMessage::data_indefinite_route_request(2));
+
+        System::current().stop_with_code(0);
+        system.run();
+
+        let result = data_route.wait().unwrap().unwrap();
+        let contract_address = contract_address(DEFAULT_CHAIN_ID);
+        let expected_response = RouteQueryResponse {
+            route: Route::round_trip(
+                segment(&[p, q, r], &Component::ProxyClient),
+                segment(&[r, q, p], &Component::ProxyServer),
+                cryptde,
+                consuming_wallet_opt,
+                0,
+                Some(contract_address),
+            )
+            .unwrap(),
+            expected_services: ExpectedServices::RoundTrip(
+                vec![
+                    ExpectedService::Nothing,
+                    ExpectedService::Routing(
+                        q.public_key().clone(),
+                        q.earning_wallet(),
+                        rate_pack(3456),
+                    ),
+                    ExpectedService::Exit(
+                        r.public_key().clone(),
+                        r.earning_wallet(),
+                        rate_pack(4567),
+                    ),
+                ],
+                vec![
+                    ExpectedService::Exit(
+                        r.public_key().clone(),
+                        r.earning_wallet(),
+                        rate_pack(4567),
+                    ),
+                    ExpectedService::Routing(
+                        q.public_key().clone(),
+                        q.earning_wallet(),
+                        rate_pack(3456),
+                    ),
+                    ExpectedService::Nothing,
+                ],
+                0,
+            ),
+        };
+        assert_eq!(expected_response, result);
+    }
+
+    #[test]
+    fn sort_routes_by_desirable_exit_nodes() {
+        let mut subject = make_standard_subject();
+
+        let us = subject.neighborhood_database.root().clone();
+        let routing_node = make_node_record(0000, true);
+        let desirable_node = make_node_record(1111, false);
+        let mut undesirable_node = make_node_record(2222, false);
+        undesirable_node.set_desirable(false);
+
+        subject
+            .neighborhood_database
+            .add_node(routing_node.clone())
+            .unwrap();
+        subject
+            .neighborhood_database
+            .add_node(undesirable_node.clone())
+            .unwrap();
+        subject
+            .neighborhood_database
+            .add_node(desirable_node.clone())
+            .unwrap();
+
+        let mut node_sequences = Vec::new();
+        node_sequences.push(vec![
+            us.public_key(),
+            routing_node.public_key(),
+            undesirable_node.public_key(),
+        ]);
+        node_sequences.push(vec![
+            us.public_key(),
+            routing_node.public_key(),
+            desirable_node.public_key(),
+        ]);
+
+        subject.sort_routes_by_desirable_exit_nodes(&mut node_sequences);
+
+        assert_eq!(desirable_node.public_key(), node_sequences[0][2]);
+        assert_eq!(undesirable_node.public_key(), node_sequences[1][2]);
+    }
+
+    #[test]
+    fn sort_routes_by_link_cost_prefers_fast_links_only_once_something_is_measured() {
+        let mut subject = make_standard_subject();
+        let us = subject.neighborhood_database.root().clone();
+        let slow_node = make_node_record(1111, true);
+        let fast_node = make_node_record(2222, true);
+        let exit_node = make_node_record(3333, false);
+        {
+            let db = &mut subject.neighborhood_database;
+            db.add_node(slow_node.clone()).unwrap();
+            db.add_node(fast_node.clone()).unwrap();
+            db.add_node(exit_node.clone()).unwrap();
+        }
+        let mut node_sequences = vec![
+            vec![
+                us.public_key(),
+                slow_node.public_key(),
+                exit_node.public_key(),
+            ],
+            vec![
+                us.public_key(),
+                fast_node.public_key(),
+                exit_node.public_key(),
+            ],
+        ];
+
+        subject.sort_routes_by_link_cost(&mut node_sequences);
+
+        assert_eq!(node_sequences[0][1], slow_node.public_key());
+        let mut link_performance = BTreeMap::new();
+        link_performance.insert(
+            slow_node.public_key().clone(),
+            LinkPerformance {
+                latency_ms: 400,
+                throughput_kbps: 64,
+            },
+        );
+        link_performance.insert(
+            fast_node.public_key().clone(),
+            LinkPerformance {
+                latency_ms: 20,
+                throughput_kbps: 4096,
+            },
+        );
+        subject
+            .neighborhood_database
+            .root_mut()
+            .set_link_performance(link_performance);
+
+        subject.sort_routes_by_link_cost(&mut node_sequences);
+
+        assert_eq!(node_sequences[0][1], fast_node.public_key());
+        assert_eq!(node_sequences[1][1], slow_node.public_key());
+    }
+
+    #[test]
+    fn sort_routes_by_trust_prefers_routes_whose_least_trusted_node_is_more_trusted() {
+        let mut subject = make_standard_subject();
+        let us = subject.neighborhood_database.root().clone();
+        let mut trusted_node = make_node_record(1212, true);
+        trusted_node.set_earning_wallet(make_wallet("trusted relay"));
+        let stranger_node = make_node_record(2323, true);
+        let mut trusted_exit = make_node_record(3434, false);
+        trusted_exit.set_earning_wallet(make_wallet("trusted exit"));
+        let good_record = TrustRecord {
+            payments_on_time: 20,
+            accurate_gossip: 100,
+            ..TrustRecord::default()
+        };
+        TRUST_CACHE.insert(&trusted_node.earning_wallet(), good_record);
+        TRUST_CACHE.insert(&trusted_exit.earning_wallet(), good_record);
+        {
+            let db = &mut subject.neighborhood_database;
+            db.add_node(trusted_node.clone()).unwrap();
+            db.add_node(stranger_node.clone()).unwrap();
+            db.add_node(trusted_exit.clone()).unwrap();
+        }
+        let mut node_sequences = vec![
+            vec![
+                us.public_key(),
+                stranger_node.public_key(),
+                trusted_exit.public_key(),
+            ],
+            vec![
+                us.public_key(),
+                trusted_node.public_key(),
+                trusted_exit.public_key(),
+            ],
+        ];
+
+        subject.sort_routes_by_trust(&mut node_sequences);
+
+        assert_eq!(node_sequences[0][1], trusted_node.public_key());
+        assert_eq!(node_sequences[1][1], stranger_node.public_key());
+    }
+
+    #[test]
+    fn prune_excess_neighbors_keeps_the_neighbors_that_earn_the_most() {
+        let mut subject = make_standard_subject();
+        let root_key = subject.neighborhood_database.root().public_key().clone();
+        let mut payer = make_node_record(1000, true);
+        payer.set_earning_wallet(make_wallet("prune_excess payer"));
+        let mut debtor = make_node_record(2000, true);
+        debtor.set_earning_wallet(make_wallet("prune_excess debtor"));
+        let strangers = (0..5u8)
+            .map(|index| {
+                let mut stranger = make_node_record(3000 + index as u16, true);
+                stranger.set_earning_wallet(make_wallet(&format!("prune_excess {}", index)));
+                stranger
+            })
+            .collect::<Vec<NodeRecord>>();
+        EARNINGS_CACHE.add_payment(&payer.earning_wallet(), 100);
+        EARNINGS_CACHE.add_charge(&debtor.earning_wallet(), 100);
+        {
+            let db = &mut subject.neighborhood_database;
+            vec![&payer, &debtor]
+                .into_iter()
+                .chain(strangers.iter())
+                .for_each(|node| {
+                    db.add_node(node.clone()).unwrap();
+                    db.add_arbitrary_half_neighbor(&root_key, node.public_key());
+                });
+            // The more neighbors a stranger has, the less it needs this Node
+            strangers.iter().enumerate().for_each(|(index, stranger)| {
+                (0..index as u8).for_each(|n| {
+                    db.add_arbitrary_half_neighbor(
+                        stranger.public_key(),
+                        &PublicKey::new(&[index as u8, n, 99]),
+                    );
+                })
+            });
+        }
+
+        subject.prune_excess_neighbors();
+
+        let kept = subject
+            .neighborhood_database
+            .root()
+            .half_neighbor_keys()
+            .into_iter()
+            .cloned()
+            .collect::<HashSet<PublicKey>>();
+        assert_eq!(
+            kept,
+            vec![
+                payer.public_key().clone(),
+                debtor.public_key().clone(),
+                strangers[0].public_key().clone(),
+                strangers[1].public_key().clone(),
+                strangers[2].public_key().clone(),
+            ]
+            .into_iter()
+            .collect::<HashSet<PublicKey>>()
+        );
+        assert!(subject
+            .neighborhood_database
+            .node_by_key(strangers[4].public_key())
+            .is_some());
+    }
+
+    #[test]
+    #[should_panic(expected = "Unable to sort routes by desirable exit nodes: Missing routes.")]
+    fn sort_routes_by_desirable_exit_nodes_panics_with_empty_node_sequences() {
+        let subject = make_standard_subject();
+
+        let mut node_sequences: Vec<Vec<&PublicKey>> = Vec::new();
+        subject.sort_routes_by_desirable_exit_nodes(&mut node_sequences);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Unable to sort routes by desirable exit nodes: Missing route segments."
+    )]
+    fn sort_routes_by_desirable_exit_nodes_panics_with_the_first_route_segment_empty() {
+        let subject = make_standard_subject();
+
+        let mut node_sequences: Vec<Vec<&PublicKey>> = Vec::new();
+        let public_key = &PublicKey::from(&b"1234"[..]);
+        node_sequences.push(vec![]);
+        node_sequences.push(vec![public_key]);
+
+        subject.sort_routes_by_desirable_exit_nodes(&mut node_sequences);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Unable to sort routes by desirable exit nodes: Missing route segments."
+    )]
+    fn sort_routes_by_desirable_exit_nodes_panics_with_the_second_route_segment_empty() {
+        let subject = make_standard_subject();
+
+        let mut node_sequences: Vec<Vec<&PublicKey>> = Vec::new();
+        let public_key = &PublicKey::from(&b"1234"[..]);
+        node_sequences.push(vec![public_key]);
+        node_sequences.push(vec![]);
+
+        subject.sort_routes_by_desirable_exit_nodes(&mut node_sequences);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Unable to sort routes by desirable exit nodes: Missing NodeRecord for public key: [MTIzNA]"
+    )]
+    fn sort_routes_by_desirable_exit_nodes_panics_when_node_record_is_missing() {
+        let subject = make_standard_subject();
+
+        let mut node_sequences: Vec<Vec<&PublicKey>> = Vec::new();
+        let public_key = &PublicKey::from(&b"1234"[..]);
+        node_sequences.push(vec![public_key]);
+        node_sequences.push(vec![public_key]);
+        println!("{}", public_key);
+
+        subject.sort_routes_by_desirable_exit_nodes(&mut node_sequences);
+    }
+
+    #[test]
+    fn compose_route_query_response_returns_an_error_when_route_segment_is_empty() {
+        let mut subject = make_standard_subject();
+
+        let result: Result<RouteQueryResponse, String> = subject.compose_route_query_response(
+            RouteSegment::new(vec![], Component::Neighborhood),
+            RouteSegment::new(vec![], Component::Neighborhood),
+        );
+        assert!(result.is_err());
+        let error_expectation: String = result.expect_err("Expected an Err but got:");
+        assert_eq!(
+            error_expectation,
+            "Cannot make multi-hop route without segment keys"
+        );
+    }
+
+    #[test]
+    fn next_return_route_id_wraps_around() {
+        let mut subject = make_standard_subject();
+        subject.next_return_route_id = 0xFFFFFFFF;
+
+        let end = subject.advance_return_route_id();
+        let beginning = subject.advance_return_route_id();
+
+        assert_eq!(end, 0xFFFFFFFF);
+        assert_eq!(beginning, 0x00000000);
+    }
+
+    /*
+            Database:
+
+                 O---R---E
+
+            Tests will be written from the viewpoint of O.
+    */
+
+    #[test]
+    fn return_route_ids_increase() {
+        let cryptde = main_cryptde();
+        let system = System::new("return_route_ids_increase");
+        let (_, _, _, subject) = make_o_r_e_subject();
+
+        let addr: Addr<Neighborhood> = subject.start();
+        let sub: Recipient<RouteQueryMessage> = addr.recipient::<RouteQueryMessage>();
+
+        let data_route_0 = sub.send(RouteQueryMessage::data_indefinite_route_request(2));
+        let data_route_1 = sub.send(RouteQueryMessage::data_indefinite_route_request(2));
+
+        System::current().stop_with_code(0);
+        system.run();
+
+        let result_0 = data_route_0.wait().unwrap().unwrap();
+        let result_1 = data_route_1.wait().unwrap().unwrap();
+        let juicy_parts = |result: RouteQueryResponse| {
+            let last_element = result.route.hops.last().unwrap();
+            let last_element_dec = cryptde.decode(last_element).unwrap();
+            let network_return_route_id: u32 =
+                serde_cbor::de::from_slice(last_element_dec.as_slice()).unwrap();
+            let metadata_return_route_id = match result.expected_services {
+                ExpectedServices::RoundTrip(_, _, id) => id,
+                _ => panic!("expected RoundTrip got OneWay"),
+            };
+            (network_return_route_id, metadata_return_route_id)
+        };
+        assert_eq!(juicy_parts(result_0), (0, 0));
+        assert_eq!(juicy_parts(result_1), (1, 1));
+    }
+
+    #[test]
+    fn can_update_consuming_wallet() {
+        let cryptde = main_cryptde();
+        let system = System::new("can_update_consuming_wallet");
+        let (o, r, e, subject) = make_o_r_e_subject();
+        let addr: Addr<Neighborhood> = subject.start();
+        let set_wallet_sub = addr.clone().recipient::<SetConsumingWalletMessage>();
+        let route_sub = addr.recipient::<RouteQueryMessage>();
+        let expected_new_wallet = make_paying_wallet(b"new consuming wallet");
+        let expected_before_route = Route::round_trip(
+            segment(&[&o, &r, &e], &Component::ProxyClient),
+            segment(&[&e, &r, &o], &Component::ProxyServer),
+            cryptde,
+            Some(make_paying_wallet(b"consuming")),
+            0,
+            Some(contract_address(DEFAULT_CHAIN_ID)),
+        )
+        .unwrap();
+        let expected_after_route = Route::round_trip(
+            segment(&[&o, &r, &e], &Component::ProxyClient),
+            segment(&[&e, &r, &o], &Component::ProxyServer),
+            cryptde,
+            Some(expected_new_wallet.clone()),
+            1,
+            Some(contract_address(DEFAULT_CHAIN_ID)),
+        )
+        .unwrap();
+
+        let route_request_1 = route_sub.send(RouteQueryMessage::data_indefinite_route_request(2));
+        let _ = set_wallet_sub.try_send(SetConsumingWalletMessage {
+            wallet: expected_new_wallet,
+        });
+        let route_request_2 = route_sub.send(RouteQueryMessage::data_indefinite_route_request(2));
+
+        System::current().stop();
+        system.run();
+
+        let route_1 = route_request_1.wait().unwrap().unwrap().route;
+        let route_2 = route_request_2.wait().unwrap().unwrap().route;
+
+        assert_eq!(route_1, expected_before_route);
+        assert_eq!(route_2, expected_after_route);
+    }
+
+    #[test]
+    fn compose_route_query_response_returns_an_error_when_route_segment_keys_is_empty() {
+        let mut subject = make_standard_subject();
 
-        let _ = Neighborhood::new(cryptde, &bc);
+        let result: Result<RouteQueryResponse, String> = subject.compose_route_query_response(
+            RouteSegment::new(vec![], Component::ProxyClient),
+            RouteSegment::new(vec![], Component::ProxyServer),
+        );
+        assert!(result.is_err());
+        let error_expectation: String = result.expect_err("Expected an Err but got:");
+        assert_eq!(
+            error_expectation,
+            "Cannot make multi-hop route without segment keys"
+        );
     }
 
     #[test]
-    #[should_panic(expected = "Neighbor AQIDBA@1.2.3.4:1234 is on the mainnet blockchain")]
-    fn cant_create_non_mainnet_neighborhood_with_mainnet_neighbors() {
-        let cryptde = main_cryptde();
-        let earning_wallet = make_wallet("earning");
-        let mut bc = bc_from_nc_plus(
-            NeighborhoodConfig {
-                mode: NeighborhoodMode::ConsumeOnly(vec![NodeDescriptor::from_str(
-                    cryptde,
-                    "AQIDBA@1.2.3.4:1234",
-                )
-                .unwrap()]),
-            },
-            earning_wallet.clone(),
-            None,
-            "cant_create_non_mainnet_neighborhood_with_mainnet_neighbors",
-        );
-        bc.blockchain_bridge_config.chain_id = chain_id_from_name(TEST_DEFAULT_CHAIN_NAME);
+    fn compose_route_query_response_returns_an_error_when_the_neighbor_is_none() {
+        let mut subject = make_standard_subject();
 
-        let _ = Neighborhood::new(cryptde, &bc);
+        let result: Result<RouteQueryResponse, String> = subject.compose_route_query_response(
+            RouteSegment::new(vec![&PublicKey::new(&[3, 3, 8])], Component::ProxyClient),
+            RouteSegment::new(vec![&PublicKey::new(&[8, 3, 3])], Component::ProxyServer),
+        );
+        assert!(result.is_err());
+        let error_expectation: String = result.expect_err("Expected an Err but got:");
+        assert_eq!(
+            error_expectation,
+            "Cannot make multi_hop with unknown neighbor"
+        );
+        assert_eq!(subject.next_return_route_id, 0);
     }
 
     #[test]
-    fn node_with_zero_hop_config_creates_single_node_database() {
-        let cryptde = main_cryptde();
-        let earning_wallet = make_wallet("earning");
+    fn calculate_expected_service_returns_error_when_given_empty_segment() {
+        let mut subject = make_standard_subject();
+        let a = &make_node_record(3456, true);
+        let db = &mut subject.neighborhood_database;
+        db.add_node(a.clone()).unwrap();
 
-        let subject = Neighborhood::new(
-            cryptde,
-            &bc_from_nc_plus(
-                NeighborhoodConfig {
-                    mode: NeighborhoodMode::ZeroHop,
-                },
-                earning_wallet.clone(),
-                None,
-                "node_with_zero_hop_config_creates_single_node_database",
-            ),
+        let result = subject.calculate_expected_service(a.public_key(), None, None);
+        assert!(result.is_err());
+        assert_eq!(
+            result.unwrap_err(),
+            "cannot calculate expected service, no keys provided in route segment"
         );
+    }
 
-        let root_node_record_ref = subject.neighborhood_database.root();
+    /*
+            Database:
 
-        assert_eq!(root_node_record_ref.public_key(), cryptde.public_key());
-        assert_eq!(root_node_record_ref.node_addr_opt(), None);
-        assert_eq!(root_node_record_ref.half_neighbor_keys().len(), 0);
-    }
+            Q---p---R
+                |   |
+            t---S---+
+
+            p is consume-only, t is originate-only.
+    */
 
     #[test]
-    fn node_with_originate_only_config_is_decentralized_with_neighbor_but_not_ip() {
-        let cryptde: &dyn CryptDE = main_cryptde();
-        let neighbor: NodeRecord = make_node_record(1234, true);
-        let earning_wallet = make_wallet("earning");
+    fn complete_routes_exercise() {
+        let mut subject = make_standard_subject();
+        let db = &mut subject.neighborhood_database;
+        db.root_mut().inner.accepts_connections = false;
+        db.root_mut().inner.routes_data = false;
+        let p = &db.root_mut().public_key().clone(); // 9e7p7un06eHs6frl5A
+        let q = &db.add_node(make_node_record(3456, true)).unwrap(); // AwQFBg
+        let r = &db.add_node(make_node_record(4567, true)).unwrap(); // BAUGBw
+        let s = &db.add_node(make_node_record(5678, true)).unwrap(); // BQYHCA
+        let t = &db
+            .add_node(make_node_record_f(6789, true, false, true))
+            .unwrap(); // BgcICQ
+        db.add_arbitrary_full_neighbor(q, p);
+        db.add_arbitrary_full_neighbor(p, r);
+        db.add_arbitrary_full_neighbor(p, s);
+        db.add_arbitrary_full_neighbor(t, s);
+        db.add_arbitrary_full_neighbor(s, r);
 
-        let subject = Neighborhood::new(
-            cryptde,
-            &bc_from_nc_plus(
-                NeighborhoodConfig {
-                    mode: NeighborhoodMode::OriginateOnly(
-                        vec![neighbor.node_descriptor(DEFAULT_CHAIN_ID, cryptde)],
-                        DEFAULT_RATE_PACK.clone(),
-                    ),
-                },
-                earning_wallet.clone(),
-                None,
-                "node_with_originate_only_config_is_decentralized_with_neighbor_but_not_ip",
-            ),
-        );
+        let contains = |routes: &Vec<Vec<&PublicKey>>, expected_keys: Vec<&PublicKey>| {
+            assert_contains(&routes, &expected_keys);
+        };
 
-        let root_node_record_ref = subject.neighborhood_database.root();
+        // At least two hops from p to anywhere standard
+        let routes = subject.complete_routes(vec![p], None, 2, RouteDirection::Over);
 
-        assert_eq!(root_node_record_ref.public_key(), cryptde.public_key());
-        assert_eq!(root_node_record_ref.accepts_connections(), false);
-        assert_eq!(root_node_record_ref.routes_data(), true);
-        assert_eq!(root_node_record_ref.node_addr_opt(), None);
-        assert_eq!(root_node_record_ref.half_neighbor_keys().len(), 0);
+        assert_eq!(routes, vec![vec![p, s, t]]);
+        // no [p, r, s] or [p, s, r] because s and r are both neighbors of p and can't exit for it
+
+        // At least two hops over from p to t
+        let routes = subject.complete_routes(vec![p], Some(t), 2, RouteDirection::Over);
+
+        contains(&routes, vec![p, s, t]);
+        contains(&routes, vec![p, r, s, t]);
+        assert_eq!(2, routes.len());
+
+        // At least two hops over from t to p
+        let routes = subject.complete_routes(vec![t], Some(p), 2, RouteDirection::Over);
+
+        assert_eq!(routes, Vec::<Vec<&PublicKey>>::new());
+        // p is consume-only; can't be an exit Node.
+
+        // At least two hops back from t to p
+        let routes = subject.complete_routes(vec![t], Some(p), 2, RouteDirection::Back);
+
+        contains(&routes, vec![t, s, p]);
+        contains(&routes, vec![t, s, r, p]);
+        assert_eq!(2, routes.len());
+        // p is consume-only, but it's the originating Node, so including it is okay
+
+        // At least two hops from p to Q - impossible
+        let routes = subject.complete_routes(vec![p], Some(q), 2, RouteDirection::Over);
+
+        assert_eq!(routes, Vec::<Vec<&PublicKey>>::new());
     }
 
     #[test]
-    fn node_with_zero_hop_config_ignores_start_message() {
-        init_test_logging();
-        let cryptde = main_cryptde();
-        let earning_wallet = make_wallet("earning");
-        let consuming_wallet = Some(make_paying_wallet(b"consuming"));
-        let system =
-            System::new("node_with_no_neighbor_configs_ignores_bootstrap_neighborhood_now_message");
-        let subject = Neighborhood::new(
-            cryptde,
-            &bc_from_nc_plus(
-                NeighborhoodConfig {
-                    mode: NeighborhoodMode::ZeroHop,
-                },
-                earning_wallet.clone(),
-                consuming_wallet.clone(),
-                "node_with_zero_hop_config_ignores_start_message",
-            ),
-        );
-        let addr: Addr<Neighborhood> = subject.start();
-        let sub = addr.clone().recipient::<StartMessage>();
-        let (hopper, _, hopper_recording_arc) = make_recorder();
-        let peer_actors = peer_actors_builder().hopper(hopper).build();
-        addr.try_send(BindMessage { peer_actors }).unwrap();
+    fn the_route_index_is_rebuilt_only_after_the_database_changes() {
+        let mut subject = make_standard_subject();
+        let first = subject.route_index();
+        let again = subject.route_index();
+        subject
+            .neighborhood_database
+            .add_node(make_node_record(3456, true))
+            .unwrap();
 
-        sub.try_send(StartMessage {}).unwrap();
+        let after_change = subject.route_index();
 
-        System::current().stop_with_code(0);
-        system.run();
-        let recording = hopper_recording_arc.lock().unwrap();
-        assert_eq!(recording.len(), 0);
-        TestLogHandler::new()
-            .exists_log_containing("INFO: Neighborhood: Empty. No Nodes to report to; continuing");
+        assert!(Arc::ptr_eq(&first, &again));
+        assert!(!Arc::ptr_eq(&first, &after_change));
+        assert_eq!(after_change.node_count(), first.node_count() + 1);
     }
 
+    /*
+            Database:
+
+            P---q---R
+
+            Test is written from the standpoint of P. Node q is non-routing.
+    */
+
     #[test]
-    #[should_panic(
-        expected = "--neighbors node descriptors must have IP address and port list, not 'AwQFBg::'"
-    )]
-    fn node_with_neighbor_config_having_no_node_addr_panics() {
-        let cryptde: &dyn CryptDE = main_cryptde();
-        let earning_wallet = make_wallet("earning");
-        let consuming_wallet = Some(make_paying_wallet(b"consuming"));
-        let neighbor_node = make_node_record(3456, true);
-        let system = System::new("node_with_bad_neighbor_config_panics");
-        let subject = Neighborhood::new(
-            cryptde,
-            &bc_from_nc_plus(
-                NeighborhoodConfig {
-                    mode: NeighborhoodMode::Standard(
-                        NodeAddr::new(&IpAddr::from_str("5.4.3.2").unwrap(), &[5678]),
-                        vec![NodeDescriptor::from((
-                            neighbor_node.public_key(),
-                            DEFAULT_CHAIN_ID == chain_id_from_name(DEFAULT_CHAIN_NAME),
-                            cryptde,
-                        ))],
-                        rate_pack(100),
-                    ),
-                },
-                earning_wallet.clone(),
-                consuming_wallet.clone(),
-                "node_with_neighbor_config_having_no_node_addr_panics",
-            ),
-        );
-        let addr: Addr<Neighborhood> = subject.start();
-        let sub = addr.clone().recipient::<StartMessage>();
-        let peer_actors = peer_actors_builder().build();
-        addr.try_send(BindMessage { peer_actors }).unwrap();
+    fn cant_route_through_non_routing_node() {
+        let mut subject = make_standard_subject();
+        let db = &mut subject.neighborhood_database;
+        let p = &db.root_mut().public_key().clone(); // 9e7p7un06eHs6frl5A
+        let q = &db
+            .add_node(make_node_record_f(4567, true, false, false))
+            .unwrap(); // BAUGBw
+        let r = &db.add_node(make_node_record(5678, true)).unwrap(); // BQYHCA
+        db.add_arbitrary_full_neighbor(p, q);
+        db.add_arbitrary_full_neighbor(q, r);
 
-        sub.try_send(StartMessage {}).unwrap();
+        // At least two hops from P to anywhere standard
+        let routes = subject.complete_routes(vec![p], None, 2, RouteDirection::Over);
 
-        System::current().stop_with_code(0);
-        system.run();
+        let expected: Vec<Vec<&PublicKey>> = vec![];
+        assert_eq!(routes, expected);
     }
 
     #[test]
-    fn neighborhood_adds_nodes_and_links() {
-        let cryptde: &dyn CryptDE = main_cryptde();
+    fn gossips_after_removing_a_neighbor() {
+        let (hopper, hopper_awaiter, hopper_recording) = make_recorder();
+        let cryptde = main_cryptde();
         let earning_wallet = make_wallet("earning");
         let consuming_wallet = Some(make_paying_wallet(b"consuming"));
-        let one_neighbor_node = make_node_record(3456, true);
-        let another_neighbor_node = make_node_record(4567, true);
-        let this_node_addr = NodeAddr::new(&IpAddr::from_str("5.4.3.2").unwrap(), &[5678]);
-
-        let subject = Neighborhood::new(
-            cryptde,
-            &bc_from_nc_plus(
-                NeighborhoodConfig {
-                    mode: NeighborhoodMode::Standard(
-                        this_node_addr.clone(),
-                        vec![
-                            NodeDescriptor::from((
-                                &one_neighbor_node,
-                                DEFAULT_CHAIN_ID == chain_id_from_name(DEFAULT_CHAIN_NAME),
-                                cryptde,
-                            )),
-                            NodeDescriptor::from((
-                                &another_neighbor_node,
-                                DEFAULT_CHAIN_ID == chain_id_from_name(DEFAULT_CHAIN_NAME),
-                                cryptde,
-                            )),
-                        ],
-                        rate_pack(100),
-                    ),
-                },
-                earning_wallet.clone(),
-                consuming_wallet.clone(),
-                "neighborhood_adds_nodes_and_links",
-            ),
-        );
-
-        let root_node_record_ref = subject.neighborhood_database.root();
-
-        assert_eq!(
-            root_node_record_ref.node_addr_opt().unwrap().clone(),
-            this_node_addr
+        let this_node = NodeRecord::new_for_tests(
+            &cryptde.public_key(),
+            Some(&NodeAddr::new(
+                &IpAddr::from_str("5.4.3.2").unwrap(),
+                &[1234],
+            )),
+            100,
+            true,
+            true,
         );
+        let this_node_inside = this_node.clone();
+        let removed_neighbor = make_node_record(2345, true);
+        let removed_neighbor_inside = removed_neighbor.clone();
+        let other_neighbor = make_node_record(3456, true);
+        let other_neighbor_inside = other_neighbor.clone();
 
-        assert_eq!(
-            root_node_record_ref.has_half_neighbor(one_neighbor_node.public_key()),
-            false,
-        );
-        assert_eq!(
-            root_node_record_ref.has_half_neighbor(another_neighbor_node.public_key()),
-            false,
-        );
-        assert_eq!(
-            subject.initial_neighbors,
-            vec![
-                NodeDescriptor::from((
-                    &one_neighbor_node,
-                    DEFAULT_CHAIN_ID == chain_id_from_name(DEFAULT_CHAIN_NAME),
-                    cryptde,
-                )),
-                NodeDescriptor::from((
-                    &another_neighbor_node,
-                    DEFAULT_CHAIN_ID == chain_id_from_name(DEFAULT_CHAIN_NAME),
-                    cryptde,
-                ))
-            ]
-        );
-    }
+        thread::spawn(move || {
+            let system = System::new("gossips_after_removing_a_neighbor");
+            let mut subject = Neighborhood::new(
+                cryptde,
+                &bc_from_nc_plus(
+                    NeighborhoodConfig {
+                        mode: NeighborhoodMode::Standard(
+                            this_node_inside.node_addr_opt().unwrap(),
+                            vec![],
+                            rate_pack(100),
+                        ),
+                    },
+                    earning_wallet.clone(),
+                    consuming_wallet.clone(),
+                    "gossips_after_removing_a_neighbor",
+                ),
+            );
+            let db = &mut subject.neighborhood_database;
 
-    #[test]
-    fn gossip_failures_eventually_stop_the_neighborhood() {
-        init_test_logging();
-        let cryptde: &dyn CryptDE = main_cryptde();
-        let earning_wallet = make_wallet("earning");
-        let one_neighbor_node: NodeRecord = make_node_record(3456, true);
-        let another_neighbor_node: NodeRecord = make_node_record(4567, true);
-        let this_node_addr = NodeAddr::new(&IpAddr::from_str("5.4.3.2").unwrap(), &[5678]);
+            db.add_node(removed_neighbor_inside.clone()).unwrap();
+            db.add_node(other_neighbor_inside.clone()).unwrap();
+            db.add_arbitrary_full_neighbor(
+                &cryptde.public_key(),
+                removed_neighbor_inside.public_key(),
+            );
+            db.add_arbitrary_full_neighbor(
+                &cryptde.public_key(),
+                other_neighbor_inside.public_key(),
+            );
+            db.add_arbitrary_full_neighbor(
+                removed_neighbor_inside.public_key(),
+                other_neighbor_inside.public_key(),
+            );
 
-        let subject = Neighborhood::new(
-            cryptde,
-            &bc_from_nc_plus(
-                NeighborhoodConfig {
-                    mode: NeighborhoodMode::Standard(
-                        this_node_addr.clone(),
-                        vec![
-                            NodeDescriptor::from((
-                                &one_neighbor_node,
-                                DEFAULT_CHAIN_ID == chain_id_from_name(DEFAULT_CHAIN_NAME),
-                                cryptde,
-                            )),
-                            NodeDescriptor::from((
-                                &another_neighbor_node,
-                                DEFAULT_CHAIN_ID == chain_id_from_name(DEFAULT_CHAIN_NAME),
-                                cryptde,
-                            )),
-                        ],
-                        rate_pack(100),
-                    ),
-                },
-                earning_wallet.clone(),
-                None,
-                "gossip_failures_eventually_stop_the_neighborhood",
-            ),
-        );
-        let ecp1 = ExpiredCoresPackage::new(
-            one_neighbor_node.node_addr_opt().unwrap().into(),
-            None,
-            make_meaningless_route(),
-            GossipFailure_0v1::NoNeighbors,
-            0,
-        );
-        let ecp2 = ExpiredCoresPackage::new(
-            another_neighbor_node.node_addr_opt().unwrap().into(),
-            None,
-            make_meaningless_route(),
-            GossipFailure_0v1::ManualRejection,
-            0,
-        );
-        let system = System::new("responds_with_none_when_initially_configured_with_no_data");
-        let addr: Addr<Neighborhood> = subject.start();
-        let sub = addr.recipient::<ExpiredCoresPackage<GossipFailure_0v1>>();
+            let addr: Addr<Neighborhood> = subject.start();
+            let peer_actors = peer_actors_builder().hopper(hopper).build();
+            addr.try_send(BindMessage { peer_actors }).unwrap();
 
-        sub.try_send(ecp1).unwrap();
-        sub.try_send(ecp2).unwrap();
+            let sub: Recipient<RemoveNeighborMessage> = addr.recipient::<RemoveNeighborMessage>();
+            sub.try_send(RemoveNeighborMessage {
+                public_key: removed_neighbor_inside.public_key().clone(),
+            })
+            .unwrap();
 
-        system.run(); // If this never halts, it's because the Neighborhood isn't properly killing its actor
+            system.run();
+        });
 
-        let tlh = TestLogHandler::new();
-        tlh.exists_log_containing ("WARN: Neighborhood: Node at 3.4.5.6 refused Debut: No neighbors for Introduction or Pass");
-        tlh.exists_log_containing ("WARN: Neighborhood: Node at 4.5.6.7 refused Debut: Node owner manually rejected your Debut");
-        tlh.exists_log_containing ("ERROR: Neighborhood: None of the Nodes listed in the --neighbors parameter could accept your Debut; shutting down");
-    }
+        let other_neighbor_cryptde =
+            CryptDENull::from(other_neighbor.public_key(), DEFAULT_CHAIN_ID);
+        hopper_awaiter.await_message_count(1);
+        let locked_recording = hopper_recording.lock().unwrap();
+        let package: &IncipientCoresPackage = locked_recording.get_record(0);
+        let gossip = match decodex(&other_neighbor_cryptde, &package.payload).unwrap() {
+            MessageType::Gossip(vd) => Gossip_0v1::try_from(vd).unwrap(),
+            x => panic!("Expected MessageType::Gossip, got {:?}", x),
+        };
+        type Digest = (PublicKey, Vec<u8>, bool, u32, Vec<PublicKey>);
+        let to_actual_digest = |gnr: GossipNodeRecord| {
+            let node_addr_opt = gnr.node_addr_opt.clone();
+            let inner = NodeRecordInner_0v1::try_from(gnr).unwrap();
+            let neighbors_vec = inner.neighbors.into_iter().collect::<Vec<PublicKey>>();
+            (
+                inner.public_key.clone(),
+                inner.public_key.into(),
+                node_addr_opt.is_some(),
+                inner.version,
+                neighbors_vec,
+            )
+        };
 
-    #[test]
-    fn node_query_responds_with_none_when_initially_configured_with_no_data() {
-        let system = System::new("responds_with_none_when_initially_configured_with_no_data");
-        let subject = make_standard_subject();
-        let addr: Addr<Neighborhood> = subject.start();
-        let sub: Recipient<NodeQueryMessage> = addr.recipient::<NodeQueryMessage>();
+        let sort_digests = |digests: Vec<Digest>| {
+            let mut digests = digests
+                .into_iter()
+                .map(|mut d| {
+                    d.4.sort_unstable_by(|a, b| a.cmp(&b));
+                    d
+                })
+                .collect_vec();
+            digests.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+            digests
+        };
 
-        let future = sub.send(NodeQueryMessage::PublicKey(PublicKey::new(&b"booga"[..])));
+        let actual_digests = sort_digests(
+            gossip
+                .node_records
+                .into_iter()
+                .map(|gnr| to_actual_digest(gnr))
+                .collect::<Vec<Digest>>(),
+        );
 
-        System::current().stop_with_code(0);
-        system.run();
-        let result = future.wait().unwrap();
-        assert_eq!(result.is_none(), true);
+        let expected_digests = sort_digests(vec![
+            (
+                removed_neighbor.public_key().clone(),
+                removed_neighbor.public_key().clone().into(),
+                false,
+                0,
+                vec![
+                    other_neighbor.public_key().clone(),
+                    this_node.public_key().clone(),
+                ],
+            ),
+            (
+                this_node.public_key().clone(),
+                this_node.public_key().clone().into(),
+                true,
+                1,
+                vec![other_neighbor.public_key().clone()],
+            ),
+        ]);
+
+        assert_eq!(expected_digests, actual_digests);
     }
 
     #[test]
-    fn node_query_responds_with_none_when_key_query_matches_no_configured_data() {
-        let cryptde: &dyn CryptDE = main_cryptde();
-        let earning_wallet = make_wallet("earning");
-        let consuming_wallet = Some(make_paying_wallet(b"consuming"));
-        let system =
-            System::new("node_query_responds_with_none_when_key_query_matches_no_configured_data");
-        let subject = Neighborhood::new(
-            cryptde,
-            &bc_from_nc_plus(
-                NeighborhoodConfig {
-                    mode: NeighborhoodMode::Standard(
-                        NodeAddr::new(&IpAddr::from_str("5.4.3.2").unwrap(), &[5678]),
-                        vec![NodeDescriptor::from((
-                            &PublicKey::new(&b"booga"[..]),
-                            &NodeAddr::new(&IpAddr::from_str("1.2.3.4").unwrap(), &[1234, 2345]),
-                            DEFAULT_CHAIN_ID == chain_id_from_name(DEFAULT_CHAIN_NAME),
-                            cryptde,
-                        ))],
-                        rate_pack(100),
-                    ),
-                },
-                earning_wallet.clone(),
-                consuming_wallet.clone(),
-                "node_query_responds_with_none_when_key_query_matches_no_configured_data",
-            ),
-        );
+    fn neighborhood_calls_gossip_acceptor_when_gossip_is_received() {
+        let handle_params_arc = Arc::new(Mutex::new(vec![]));
+        let gossip_acceptor = GossipAcceptorMock::new()
+            .handle_params(&handle_params_arc)
+            .handle_result(GossipAcceptanceResult::Ignored);
+        let subject_node = make_global_cryptde_node_record(1234, true); // 9e7p7un06eHs6frl5A
+        let neighbor = make_node_record(1111, true);
+        let mut subject = neighborhood_from_nodes(&subject_node, Some(&neighbor));
+        subject.gossip_acceptor = Box::new(gossip_acceptor);
+        let gossip = GossipBuilder::new(&subject.neighborhood_database)
+            .node(subject_node.public_key(), true)
+            .build();
+        let cores_package = ExpiredCoresPackage {
+            immediate_neighbor: subject_node.node_addr_opt().unwrap().into(),
+            paying_wallet: None,
+            remaining_route: make_meaningless_route(),
+            payload: gossip.clone(),
+            payload_len: 0,
+        };
+        let system = System::new("");
         let addr: Addr<Neighborhood> = subject.start();
-        let sub: Recipient<NodeQueryMessage> = addr.recipient::<NodeQueryMessage>();
+        let peer_actors = peer_actors_builder().build();
+        addr.try_send(BindMessage { peer_actors }).unwrap();
+        let sub = addr.recipient::<ExpiredCoresPackage<Gossip_0v1>>();
 
-        let future = sub.send(NodeQueryMessage::PublicKey(PublicKey::new(&b"blah"[..])));
+        sub.try_send(cores_package).unwrap();
 
-        System::current().stop_with_code(0);
+        System::current().stop();
         system.run();
-        let result = future.wait().unwrap();
-        assert_eq!(result.is_none(), true);
+        let mut handle_params = handle_params_arc.lock().unwrap();
+        let (call_database, call_agrs, call_gossip_source) = handle_params.remove(0);
+        assert!(handle_params.is_empty());
+        assert_eq!(&subject_node, call_database.root());
+        assert_eq!(1, call_database.keys().len());
+        let agrs: Vec<AccessibleGossipRecord> = gossip.try_into().unwrap();
+        assert_eq!(agrs, call_agrs);
+        let actual_gossip_source: SocketAddr = subject_node.node_addr_opt().unwrap().into();
+        assert_eq!(actual_gossip_source, call_gossip_source);
     }
 
     #[test]
-    fn node_query_responds_with_result_when_key_query_matches_configured_data() {
-        let cryptde = main_cryptde();
-        let earning_wallet = make_wallet("earning");
-        let consuming_wallet = Some(make_paying_wallet(b"consuming"));
-        let system =
-            System::new("node_query_responds_with_result_when_key_query_matches_configured_data");
-        let one_neighbor = make_node_record(2345, true);
-        let another_neighbor = make_node_record(3456, true);
-        let mut subject = Neighborhood::new(
-            cryptde,
-            &bc_from_nc_plus(
-                NeighborhoodConfig {
-                    mode: NeighborhoodMode::Standard(
-                        NodeAddr::new(&IpAddr::from_str("5.4.3.2").unwrap(), &[5678]),
-                        vec![node_record_to_neighbor_config(&one_neighbor)],
-                        rate_pack(100),
-                    ),
-                },
-                earning_wallet.clone(),
-                consuming_wallet.clone(),
-                "node_query_responds_with_result_when_key_query_matches_configured_data",
-            ),
-        );
+    fn neighborhood_sends_only_an_acceptance_debut_when_an_acceptance_debut_is_provided() {
+        let introduction_target_node = make_node_record(7345, true);
+        let subject_node = make_global_cryptde_node_record(5555, true); // 9e7p7un06eHs6frl5A
+        let neighbor = make_node_record(1050, true);
+        let mut subject = neighborhood_from_nodes(&subject_node, Some(&neighbor));
         subject
             .neighborhood_database
-            .add_node(another_neighbor.clone())
+            .add_node(introduction_target_node.clone())
             .unwrap();
-        let addr: Addr<Neighborhood> = subject.start();
-        let sub: Recipient<NodeQueryMessage> = addr.recipient::<NodeQueryMessage>();
 
-        let future = sub.send(NodeQueryMessage::PublicKey(
-            another_neighbor.public_key().clone(),
-        ));
+        subject.neighborhood_database.add_arbitrary_half_neighbor(
+            subject_node.public_key(),
+            introduction_target_node.public_key(),
+        );
+        let debut = GossipBuilder::new(&subject.neighborhood_database)
+            .node(subject_node.public_key(), true)
+            .build();
+        let gossip_acceptor =
+            GossipAcceptorMock::new().handle_result(GossipAcceptanceResult::Reply(
+                debut.clone(),
+                introduction_target_node.public_key().clone(),
+                introduction_target_node.node_addr_opt().unwrap(),
+            ));
+        subject.gossip_acceptor = Box::new(gossip_acceptor);
+        let (hopper, _, hopper_recording_arc) = make_recorder();
+        let peer_actors = peer_actors_builder().hopper(hopper).build();
+        let system = System::new("");
+        subject.hopper_no_lookup = Some(peer_actors.hopper.from_hopper_client_no_lookup);
 
-        System::current().stop_with_code(0);
-        system.run();
-        let result = future.wait().unwrap();
-        assert_eq!(
-            result.unwrap(),
-            NodeQueryResponseMetadata::new(
-                another_neighbor.public_key().clone(),
-                Some(another_neighbor.node_addr_opt().unwrap().clone()),
-                another_neighbor.rate_pack().clone(),
-            )
+        subject.handle_gossip(
+            Gossip_0v1::new(vec![]),
+            SocketAddr::from_str("1.1.1.1:1111").unwrap(),
         );
+
+        System::current().stop();
+        system.run();
+        let hopper_recording = hopper_recording_arc.lock().unwrap();
+        let package = hopper_recording.get_record::<NoLookupIncipientCoresPackage>(0);
+        assert_eq!(1, hopper_recording.len());
+        assert_eq!(introduction_target_node.public_key(), &package.public_key);
+        let gossip = match decodex::<MessageType>(
+            &CryptDENull::from(introduction_target_node.public_key(), DEFAULT_CHAIN_ID),
+            &package.payload,
+        ) {
+            Ok(MessageType::Gossip(vd)) => Gossip_0v1::try_from(vd).unwrap(),
+            x => panic!("Wanted Gossip, found {:?}", x),
+        };
+        assert_eq!(debut, gossip);
     }
 
     #[test]
-    fn node_query_responds_with_none_when_ip_address_query_matches_no_configured_data() {
-        let cryptde: &dyn CryptDE = main_cryptde();
-        let earning_wallet = make_wallet("earning");
-        let consuming_wallet = Some(make_paying_wallet(b"consuming"));
-        let system = System::new(
-            "node_query_responds_with_none_when_ip_address_query_matches_no_configured_data",
-        );
-        let subject = Neighborhood::new(
-            cryptde,
-            &bc_from_nc_plus(
-                NeighborhoodConfig {
-                    mode: NeighborhoodMode::Standard(
-                        NodeAddr::new(&IpAddr::from_str("5.4.3.2").unwrap(), &[5678]),
-                        vec![NodeDescriptor::from((
-                            &PublicKey::new(&b"booga"[..]),
-                            &NodeAddr::new(&IpAddr::from_str("1.2.3.4").unwrap(), &[1234, 2345]),
-                            DEFAULT_CHAIN_ID == chain_id_from_name(DEFAULT_CHAIN_NAME),
-                            cryptde,
-                        ))],
-                        rate_pack(100),
-                    ),
-                },
-                earning_wallet.clone(),
-                consuming_wallet.clone(),
-                "node_query_responds_with_none_when_ip_address_query_matches_no_configured_data",
-            ),
-        );
-        let addr: Addr<Neighborhood> = subject.start();
-        let sub: Recipient<NodeQueryMessage> = addr.recipient::<NodeQueryMessage>();
+    fn neighborhood_transmits_gossip_failure_properly() {
+        let subject_node = make_global_cryptde_node_record(5555, true); // 9e7p7un06eHs6frl5A
+        let neighbor = make_node_record(1111, true);
+        let public_key = PublicKey::new(&[1, 2, 3, 4]);
+        let node_addr = NodeAddr::from_str("1.2.3.4:1234").unwrap();
+        let gossip_acceptor =
+            GossipAcceptorMock::new().handle_result(GossipAcceptanceResult::Failed(
+                GossipFailure_0v1::NoSuitableNeighbors,
+                public_key.clone(),
+                node_addr.clone(),
+            ));
+        let mut subject: Neighborhood = neighborhood_from_nodes(&subject_node, Some(&neighbor));
+        let (hopper, _, hopper_recording_arc) = make_recorder();
+        let system = System::new("neighborhood_transmits_gossip_failure_properly");
+        let peer_actors = peer_actors_builder().hopper(hopper).build();
+        subject.hopper_no_lookup = Some(peer_actors.hopper.from_hopper_client_no_lookup);
+        subject.gossip_acceptor = Box::new(gossip_acceptor);
 
-        let future = sub.send(NodeQueryMessage::IpAddress(
-            IpAddr::from_str("2.3.4.5").unwrap(),
-        ));
+        subject.handle_gossip_agrs(vec![], SocketAddr::from_str("1.2.3.4:1234").unwrap());
 
-        System::current().stop_with_code(0);
+        System::current().stop();
         system.run();
-        let result = future.wait().unwrap();
-        assert_eq!(result.is_none(), true);
+        let hopper_recording = hopper_recording_arc.lock().unwrap();
+        let package = hopper_recording.get_record::<NoLookupIncipientCoresPackage>(0);
+        assert_eq!(1, hopper_recording.len());
+        assert_eq!(package.node_addr, node_addr);
+        let payload = decodex::<MessageType>(
+            &CryptDENull::from(&public_key, DEFAULT_CHAIN_ID),
+            &package.payload,
+        )
+        .unwrap();
+        assert_eq!(
+            payload,
+            MessageType::GossipFailure(VersionedData::new(
+                &crate::sub_lib::migrations::gossip_failure::MIGRATIONS,
+                &GossipFailure_0v1::NoSuitableNeighbors
+            ))
+        );
     }
 
-    #[test]
-    fn node_query_responds_with_result_when_ip_address_query_matches_configured_data() {
-        let cryptde: &dyn CryptDE = main_cryptde();
-        let system = System::new(
-            "node_query_responds_with_result_when_ip_address_query_matches_configured_data",
-        );
-        let node_record = make_node_record(1234, true);
-        let another_node_record = make_node_record(2345, true);
-        let mut subject = Neighborhood::new(
-            cryptde,
-            &bc_from_nc_plus(
-                NeighborhoodConfig {
-                    mode: NeighborhoodMode::Standard(
-                        node_record.node_addr_opt().unwrap(),
-                        vec![NodeDescriptor::from((
-                            &node_record,
-                            DEFAULT_CHAIN_ID == chain_id_from_name(DEFAULT_CHAIN_NAME),
-                            cryptde,
-                        ))],
-                        rate_pack(100),
-                    ),
-                },
-                node_record.earning_wallet(),
-                None,
-                "node_query_responds_with_result_when_ip_address_query_matches_configured_data",
-            ),
-        );
-        subject
-            .neighborhood_database
-            .add_node(another_node_record.clone())
-            .unwrap();
-        let addr: Addr<Neighborhood> = subject.start();
-        let sub: Recipient<NodeQueryMessage> = addr.recipient::<NodeQueryMessage>();
+    struct DatabaseReplacementGossipAcceptor {
+        pub replacement_database: NeighborhoodDatabase,
+    }
 
-        let future = sub.send(NodeQueryMessage::IpAddress(
-            IpAddr::from_str("2.3.4.5").unwrap(),
-        ));
+    impl GossipAcceptor for DatabaseReplacementGossipAcceptor {
+        fn handle(
+            &self,
+            database: &mut NeighborhoodDatabase,
+            _agrs: Vec<AccessibleGossipRecord>,
+            _gossip_source: SocketAddr,
+        ) -> GossipAcceptanceResult {
+            let non_root_database_keys = database
+                .keys()
+                .into_iter()
+                .filter(|k| *k != database.root().public_key())
+                .map(|k| k.clone())
+                .collect_vec();
+            non_root_database_keys
+                .into_iter()
+                .for_each(|k| database.remove_node(&k));
+            let database_root_neighbor_keys = database
+                .root()
+                .half_neighbor_keys()
+                .into_iter()
+                .map(|k| k.clone())
+                .collect_vec();
+            database_root_neighbor_keys.into_iter().for_each(|k| {
+                database.root_mut().remove_half_neighbor_key(&k);
+            });
+            self.replacement_database
+                .keys()
+                .into_iter()
+                .filter(|k| *k != self.replacement_database.root().public_key())
+                .for_each(|k| {
+                    database
+                        .add_node(self.replacement_database.node_by_key(k).unwrap().clone())
+                        .unwrap();
+                });
+            self.replacement_database.keys().into_iter().for_each(|k| {
+                let node_record = self.replacement_database.node_by_key(k).unwrap();
+                node_record.half_neighbor_keys().into_iter().for_each(|n| {
+                    database.add_arbitrary_half_neighbor(k, n);
+                });
+            });
+            GossipAcceptanceResult::Ignored
+        }
+    }
 
-        System::current().stop_with_code(0);
-        system.run();
-        let result = future.wait().unwrap();
-        assert_eq!(
-            result.unwrap(),
-            NodeQueryResponseMetadata::new(
-                another_node_record.public_key().clone(),
-                Some(another_node_record.node_addr_opt().unwrap().clone()),
-                another_node_record.rate_pack().clone(),
-            )
-        );
+    fn bind_subject(subject: &mut Neighborhood, peer_actors: PeerActors) {
+        subject.hopper = Some(peer_actors.hopper.from_hopper_client);
+        subject.hopper_no_lookup = Some(peer_actors.hopper.from_hopper_client_no_lookup);
+        subject.connected_signal = Some(peer_actors.accountant.start);
     }
 
     #[test]
-    fn route_query_responds_with_none_when_asked_for_route_with_too_many_hops() {
-        let system =
-            System::new("route_query_responds_with_none_when_asked_for_route_with_too_many_hops");
-        let subject = make_standard_subject();
-        let addr: Addr<Neighborhood> = subject.start();
-        let sub: Recipient<RouteQueryMessage> = addr.recipient::<RouteQueryMessage>();
+    fn neighborhood_does_not_start_accountant_if_no_route_can_be_made() {
+        let subject_node = make_global_cryptde_node_record(5555, true); // 9e7p7un06eHs6frl5A
+        let neighbor = make_node_record(1111, true);
+        let mut subject: Neighborhood = neighborhood_from_nodes(&subject_node, Some(&neighbor));
+        let mut replacement_database = subject.neighborhood_database.clone();
+        replacement_database.add_node(neighbor.clone()).unwrap();
+        replacement_database
+            .add_arbitrary_half_neighbor(subject_node.public_key(), neighbor.public_key());
+        subject.gossip_acceptor = Box::new(DatabaseReplacementGossipAcceptor {
+            replacement_database,
+        });
+        let (accountant, _, accountant_recording_arc) = make_recorder();
+        let system = System::new("neighborhood_does_not_start_accountant_if_no_route_can_be_made");
+        let peer_actors = peer_actors_builder().accountant(accountant).build();
+        bind_subject(&mut subject, peer_actors);
 
-        let future = sub.send(RouteQueryMessage::data_indefinite_route_request(5));
+        subject.handle_gossip_agrs(vec![], SocketAddr::from_str("1.2.3.4:1234").unwrap());
 
-        System::current().stop_with_code(0);
+        System::current().stop();
         system.run();
-        let result = future.wait().unwrap();
-        assert_eq!(result, None);
+        let accountant_recording = accountant_recording_arc.lock().unwrap();
+        assert_eq!(accountant_recording.len(), 0);
+        assert_eq!(subject.is_connected, false);
     }
 
     #[test]
-    fn route_query_responds_with_none_when_asked_for_two_hop_round_trip_route_without_consuming_wallet(
-    ) {
-        let system = System::new("route_query_responds_with_none_when_asked_for_two_hop_round_trip_route_without_consuming_wallet");
-        let subject = make_standard_subject();
-        let addr: Addr<Neighborhood> = subject.start();
-        let sub: Recipient<RouteQueryMessage> = addr.recipient::<RouteQueryMessage>();
+    fn neighborhood_does_not_start_accountant_if_already_connected() {
+        let subject_node = make_global_cryptde_node_record(5555, true); // 9e7p7un06eHs6frl5A
+        let neighbor = make_node_record(1111, true);
+        let mut subject: Neighborhood = neighborhood_from_nodes(&subject_node, Some(&neighbor));
+        let replacement_database = subject.neighborhood_database.clone();
+        subject.gossip_acceptor = Box::new(DatabaseReplacementGossipAcceptor {
+            replacement_database,
+        });
+        subject.is_connected = true;
+        let (accountant, _, accountant_recording_arc) = make_recorder();
+        let system = System::new("neighborhood_does_not_start_accountant_if_no_route_can_be_made");
+        let peer_actors = peer_actors_builder().accountant(accountant).build();
+        bind_subject(&mut subject, peer_actors);
 
-        let future = sub.send(RouteQueryMessage::data_indefinite_route_request(2));
+        subject.handle_gossip_agrs(vec![], SocketAddr::from_str("1.2.3.4:1234").unwrap());
 
-        System::current().stop_with_code(0);
+        System::current().stop();
         system.run();
-        let result = future.wait().unwrap();
-        assert_eq!(result, None);
+        let accountant_recording = accountant_recording_arc.lock().unwrap();
+        assert_eq!(accountant_recording.len(), 0);
+        assert_eq!(subject.is_connected, true);
     }
 
     #[test]
-    fn route_query_succeeds_when_asked_for_one_hop_round_trip_route_without_consuming_wallet() {
-        let cryptde = main_cryptde();
-        let earning_wallet = make_wallet("earning");
-        let system = System::new(
-            "route_query_succeeds_when_asked_for_one_hop_round_trip_route_without_consuming_wallet",
-        );
-        let mut subject = make_standard_subject();
-        subject
-            .neighborhood_database
-            .root_mut()
-            .set_earning_wallet(earning_wallet);
-        subject.consuming_wallet_opt = None;
-        // These happen to be extracted in the desired order. We could not think of a way to guarantee it.
-        let mut undesirable_exit_node = make_node_record(2345, true);
-        let desirable_exit_node = make_node_record(3456, false);
-        undesirable_exit_node.set_desirable(false);
-        let originating_node = &subject.neighborhood_database.root().clone();
-        {
-            let db = &mut subject.neighborhood_database;
-            db.add_node(undesirable_exit_node.clone()).unwrap();
-            db.add_node(desirable_exit_node.clone()).unwrap();
-            db.add_arbitrary_full_neighbor(
-                undesirable_exit_node.public_key(),
-                originating_node.public_key(),
-            );
-            db.add_arbitrary_full_neighbor(
-                desirable_exit_node.public_key(),
-                originating_node.public_key(),
-            );
-        }
-        let addr: Addr<Neighborhood> = subject.start();
-        let sub: Recipient<RouteQueryMessage> = addr.recipient::<RouteQueryMessage>();
-        let msg = RouteQueryMessage::data_indefinite_route_request(1);
+    fn neighborhood_starts_accountant_when_first_route_can_be_made() {
+        let subject_node = make_global_cryptde_node_record(5555, true); // 9e7p7un06eHs6frl5A
+        let relay1 = make_node_record(1111, true);
+        let relay2 = make_node_record(2222, false);
+        let exit = make_node_record(3333, false);
+        let mut subject: Neighborhood = neighborhood_from_nodes(&subject_node, Some(&relay1));
+        let mut replacement_database = subject.neighborhood_database.clone();
+        replacement_database.add_node(relay1.clone()).unwrap();
+        replacement_database.add_node(relay2.clone()).unwrap();
+        replacement_database.add_node(exit.clone()).unwrap();
+        replacement_database
+            .add_arbitrary_full_neighbor(subject_node.public_key(), relay1.public_key());
+        replacement_database.add_arbitrary_full_neighbor(relay1.public_key(), relay2.public_key());
+        replacement_database.add_arbitrary_full_neighbor(relay2.public_key(), exit.public_key());
+        subject.gossip_acceptor = Box::new(DatabaseReplacementGossipAcceptor {
+            replacement_database,
+        });
+        subject.persistent_config_opt = Some(Box::new(
+            PersistentConfigurationMock::new().set_past_neighbors_result(Ok(())),
+        ));
+        subject.is_connected = false;
+        let (accountant, _, accountant_recording_arc) = make_recorder();
+        let system = System::new("neighborhood_does_not_start_accountant_if_no_route_can_be_made");
+        let peer_actors = peer_actors_builder().accountant(accountant).build();
+        bind_subject(&mut subject, peer_actors);
 
-        let future = sub.send(msg);
+        subject.handle_gossip_agrs(vec![], SocketAddr::from_str("1.2.3.4:1234").unwrap());
 
-        System::current().stop_with_code(0);
+        System::current().stop();
         system.run();
-        let segment = |nodes: Vec<&NodeRecord>, component: Component| {
-            RouteSegment::new(
-                nodes.into_iter().map(|n| n.public_key()).collect(),
-                component,
-            )
-        };
-        let result = future.wait().unwrap().unwrap();
-        let expected_response = RouteQueryResponse {
-            route: Route::round_trip(
-                segment(
-                    vec![originating_node, &desirable_exit_node],
-                    Component::ProxyClient,
-                ),
-                segment(
-                    vec![&desirable_exit_node, originating_node],
-                    Component::ProxyServer,
-                ),
-                cryptde,
-                None,
-                0,
-                None,
-            )
-            .unwrap(),
-            expected_services: ExpectedServices::RoundTrip(
-                vec![
-                    ExpectedService::Nothing,
-                    ExpectedService::Exit(
-                        desirable_exit_node.public_key().clone(),
-                        desirable_exit_node.earning_wallet(),
-                        rate_pack(3456),
-                    ),
-                ],
-                vec![
-                    ExpectedService::Exit(
-                        desirable_exit_node.public_key().clone(),
-                        desirable_exit_node.earning_wallet(),
-                        rate_pack(3456),
-                    ),
-                    ExpectedService::Nothing,
-                ],
-                0,
-            ),
-        };
-        assert_eq!(expected_response, result);
+        let accountant_recording = accountant_recording_arc.lock().unwrap();
+        assert_eq!(accountant_recording.len(), 1);
+        assert_eq!(subject.is_connected, true);
     }
 
     #[test]
-    fn route_query_responds_with_none_when_asked_for_one_hop_round_trip_route_without_consuming_wallet_when_back_route_needs_two_hops(
-    ) {
-        let system = System::new("route_query_responds_with_none_when_asked_for_one_hop_round_trip_route_without_consuming_wallet_when_back_route_needs_two_hops");
-        let mut subject = make_standard_subject();
-        let a = &make_node_record(1234, true);
-        let b = &subject.neighborhood_database.root().clone();
-        let c = &make_node_record(3456, true);
-        {
-            let db = &mut subject.neighborhood_database;
-            db.add_node(a.clone()).unwrap();
-            db.add_node(c.clone()).unwrap();
-            let mut single_edge = |a: &NodeRecord, b: &NodeRecord| {
-                db.add_arbitrary_half_neighbor(a.public_key(), b.public_key())
-            };
-            single_edge(a, b);
-            single_edge(b, c);
-            single_edge(c, a);
-        }
-        let addr: Addr<Neighborhood> = subject.start();
-        let sub: Recipient<RouteQueryMessage> = addr.recipient::<RouteQueryMessage>();
-        let msg = RouteQueryMessage::data_indefinite_route_request(1);
+    fn warm_up_is_broadcast_when_it_changes_until_routes_are_available() {
+        let subject_node = make_global_cryptde_node_record(5555, true); // 9e7p7un06eHs6frl5A
+        let neighbor = make_node_record(1111, true);
+        let mut subject: Neighborhood = neighborhood_from_nodes(&subject_node, Some(&neighbor));
+        let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
+        let system = System::new("warm_up_is_broadcast_when_it_changes_until_routes_are_available");
+        subject.to_ui_message_sub = Some(ui_gateway.start().recipient());
 
-        let future = sub.send(msg);
+        subject.broadcast_warm_up(0, false);
+        subject.broadcast_warm_up(0, false);
+        subject
+            .neighborhood_database
+            .add_node(neighbor.clone())
+            .unwrap();
+        subject.broadcast_warm_up(1, false);
+        subject.broadcast_warm_up(1, true);
+        subject.broadcast_warm_up(0, false);
 
-        System::current().stop_with_code(0);
+        System::current().stop();
         system.run();
-        let result = future.wait().unwrap();
-        assert_eq!(result, None);
+        let ui_gateway_recording = ui_gateway_recording_arc.lock().unwrap();
+        let progress = (0..ui_gateway_recording.len())
+            .map(|index| {
+                let message = ui_gateway_recording.get_record::<NodeToUiMessage>(index);
+                assert_eq!(message.target, AllClients);
+                UiWarmUpBroadcast::fmb(message.body.clone()).unwrap().0
+            })
+            .collect_vec();
+        assert_eq!(
+            progress,
+            vec![
+                warm_up_progress(0, 0, false),
+                warm_up_progress(1, 1, false),
+                warm_up_progress(1, 1, true),
+            ]
+        );
+        assert_eq!(progress[1].progress, 40);
     }
 
     #[test]
-    fn route_query_responds_with_none_when_asked_for_two_hop_one_way_route_without_consuming_wallet(
-    ) {
-        let system = System::new("route_query_responds_with_none_when_asked_for_two_hop_one_way_route_without_consuming_wallet");
-        let subject = make_standard_subject();
-        let addr: Addr<Neighborhood> = subject.start();
-        let sub: Recipient<RouteQueryMessage> = addr.recipient::<RouteQueryMessage>();
-        let msg = RouteQueryMessage::data_indefinite_route_request(2);
+    fn connected_neighborhood_still_tells_whether_routes_are_available() {
+        let subject_node = make_global_cryptde_node_record(5555, true); // 9e7p7un06eHs6frl5A
+        let neighbor = make_node_record(1111, true);
+        let mut subject: Neighborhood = neighborhood_from_nodes(&subject_node, Some(&neighbor));
+        subject.is_connected = true;
+        let (accountant, _, accountant_recording_arc) = make_recorder();
+        let system = System::new("connected_neighborhood_still_tells_whether_routes_are_available");
+        let peer_actors = peer_actors_builder().accountant(accountant).build();
+        bind_subject(&mut subject, peer_actors);
 
-        let future = sub.send(msg);
+        let result = subject.check_connectedness();
 
-        System::current().stop_with_code(0);
+        System::current().stop();
         system.run();
-        let result = future.wait().unwrap();
-        assert_eq!(result, None);
+        assert_eq!(result, false);
+        assert_eq!(subject.is_connected, true);
+        let accountant_recording = accountant_recording_arc.lock().unwrap();
+        assert_eq!(accountant_recording.len(), 0);
+    }
+
+    struct NeighborReplacementGossipAcceptor {
+        pub new_neighbors: Vec<NodeRecord>,
+    }
+
+    impl GossipAcceptor for NeighborReplacementGossipAcceptor {
+        fn handle(
+            &self,
+            database: &mut NeighborhoodDatabase,
+            _agrs: Vec<AccessibleGossipRecord>,
+            _gossip_source: SocketAddr,
+        ) -> GossipAcceptanceResult {
+            let half_neighbor_keys = database
+                .root()
+                .half_neighbor_keys()
+                .into_iter()
+                .map(|k| k.clone())
+                .collect_vec();
+            half_neighbor_keys
+                .into_iter()
+                .for_each(|k| database.remove_node(&k));
+            let root_key = database.root().public_key().clone();
+            self.new_neighbors.iter().for_each(|nr| {
+                database.add_node(nr.clone()).unwrap();
+                database.add_arbitrary_full_neighbor(&root_key, nr.public_key());
+            });
+            GossipAcceptanceResult::Ignored
+        }
     }
 
     #[test]
-    fn route_query_responds_with_standard_zero_hop_route_when_requested() {
-        let cryptde = main_cryptde();
-        let system = System::new("responds_with_standard_zero_hop_route_when_requested");
-        let subject = make_standard_subject();
-        let addr: Addr<Neighborhood> = subject.start();
-        let sub: Recipient<RouteQueryMessage> = addr.recipient::<RouteQueryMessage>();
+    fn neighborhood_updates_past_neighbors_when_neighbor_list_changes() {
+        let cryptde: &dyn CryptDE = main_cryptde();
+        let subject_node = make_global_cryptde_node_record(5555, true); // 9e7p7un06eHs6frl5A
+        let old_neighbor = make_node_record(1111, true);
+        let new_neighbor = make_node_record(2222, true);
+        let mut subject: Neighborhood = neighborhood_from_nodes(&subject_node, Some(&old_neighbor));
+        subject
+            .neighborhood_database
+            .add_node(old_neighbor.clone())
+            .unwrap();
+        subject
+            .neighborhood_database
+            .add_arbitrary_full_neighbor(subject_node.public_key(), old_neighbor.public_key());
+        let gossip_acceptor = NeighborReplacementGossipAcceptor {
+            new_neighbors: vec![old_neighbor.clone(), new_neighbor.clone()],
+        };
+        let set_past_neighbors_params_arc = Arc::new(Mutex::new(vec![]));
+        let persistent_config = PersistentConfigurationMock::new()
+            .set_past_neighbors_params(&set_past_neighbors_params_arc)
+            .set_past_neighbors_result(Ok(()));
+        subject.gossip_acceptor = Box::new(gossip_acceptor);
+        subject.persistent_config_opt = Some(Box::new(persistent_config));
 
-        let future = sub.send(RouteQueryMessage::data_indefinite_route_request(0));
+        subject.handle_gossip_agrs(vec![], SocketAddr::from_str("1.2.3.4:1234").unwrap());
 
-        System::current().stop_with_code(0);
-        system.run();
-        let result = future.wait().unwrap().unwrap();
-        let expected_response = RouteQueryResponse {
-            route: Route::round_trip(
-                RouteSegment::new(
-                    vec![&cryptde.public_key(), &cryptde.public_key()],
-                    Component::ProxyClient,
-                ),
-                RouteSegment::new(
-                    vec![&cryptde.public_key(), &cryptde.public_key()],
-                    Component::ProxyServer,
-                ),
+        let mut set_past_neighbors_params = set_past_neighbors_params_arc.lock().unwrap();
+        let (neighbors_opt, db_password) = set_past_neighbors_params.remove(0);
+        let neighbors = neighbors_opt.unwrap();
+        assert_contains(
+            &neighbors,
+            &NodeDescriptor::from((
+                &old_neighbor,
+                DEFAULT_CHAIN_ID == chain_id_from_name(DEFAULT_CHAIN_NAME),
                 cryptde,
-                None,
-                0,
-                None,
-            )
-            .unwrap(),
-            expected_services: ExpectedServices::RoundTrip(
-                vec![ExpectedService::Nothing, ExpectedService::Nothing],
-                vec![ExpectedService::Nothing, ExpectedService::Nothing],
-                0,
-            ),
-        };
-        assert_eq!(result, expected_response);
+            )),
+        );
+        assert_contains(
+            &neighbors,
+            &NodeDescriptor::from((
+                &new_neighbor,
+                DEFAULT_CHAIN_ID == chain_id_from_name(DEFAULT_CHAIN_NAME),
+                cryptde,
+            )),
+        );
+        assert_eq!(neighbors.len(), 2);
+        assert_eq!(db_password, "password".to_string());
     }
 
     #[test]
-    fn zero_hop_routing_handles_return_route_id_properly() {
-        let mut subject = make_standard_subject();
-        let result0 = subject.zero_hop_route_response();
-        let result1 = subject.zero_hop_route_response();
+    fn neighborhood_removes_past_neighbors_when_neighbor_list_goes_empty() {
+        let subject_node = make_global_cryptde_node_record(5555, true); // 9e7p7un06eHs6frl5A
+        let neighbor = make_node_record(1111, true);
+        let mut subject: Neighborhood = neighborhood_from_nodes(&subject_node, Some(&neighbor));
+        subject
+            .neighborhood_database
+            .add_node(neighbor.clone())
+            .unwrap();
+        subject
+            .neighborhood_database
+            .add_arbitrary_full_neighbor(subject_node.public_key(), neighbor.public_key());
+        let gossip_acceptor = NeighborReplacementGossipAcceptor {
+            new_neighbors: vec![],
+        };
+        let set_past_neighbors_params_arc = Arc::new(Mutex::new(vec![]));
+        let persistent_config = PersistentConfigurationMock::new()
+            .set_past_neighbors_params(&set_past_neighbors_params_arc)
+            .set_past_neighbors_result(Ok(()));
+        subject.gossip_acceptor = Box::new(gossip_acceptor);
+        subject.persistent_config_opt = Some(Box::new(persistent_config));
 
-        let return_route_id_0 = match result0.expected_services {
-            ExpectedServices::RoundTrip(_, _, id) => id,
-            _ => panic!("expected RoundTrip got OneWay"),
+        subject.handle_gossip_agrs(vec![], SocketAddr::from_str("1.2.3.4:1234").unwrap());
+
+        let mut set_past_neighbors_params = set_past_neighbors_params_arc.lock().unwrap();
+        let (neighbors_opt, db_password) = set_past_neighbors_params.remove(0);
+        assert_eq!(neighbors_opt, None);
+        assert_eq!(db_password, "password".to_string());
+    }
+
+    #[test]
+    fn neighborhood_does_not_update_past_neighbors_when_neighbor_list_does_not_change() {
+        let subject_node = make_global_cryptde_node_record(5555, true); // 9e7p7un06eHs6frl5A
+        let steadfast_neighbor = make_node_record(1111, true);
+        let mut subject: Neighborhood =
+            neighborhood_from_nodes(&subject_node, Some(&steadfast_neighbor));
+        subject
+            .neighborhood_database
+            .add_node(steadfast_neighbor.clone())
+            .unwrap();
+        subject.neighborhood_database.add_arbitrary_full_neighbor(
+            subject_node.public_key(),
+            steadfast_neighbor.public_key(),
+        );
+        let gossip_acceptor = NeighborReplacementGossipAcceptor {
+            new_neighbors: vec![steadfast_neighbor.clone()],
         };
+        let set_past_neighbors_params_arc = Arc::new(Mutex::new(vec![]));
+        let persistent_config = PersistentConfigurationMock::new()
+            .set_past_neighbors_params(&set_past_neighbors_params_arc);
+        subject.gossip_acceptor = Box::new(gossip_acceptor);
+        subject.persistent_config_opt = Some(Box::new(persistent_config));
 
-        let return_route_id_1 = match result1.expected_services {
-            ExpectedServices::RoundTrip(_, _, id) => id,
-            _ => panic!("expected RoundTrip got OneWay"),
+        subject.handle_gossip_agrs(vec![], SocketAddr::from_str("1.2.3.4:1234").unwrap());
+
+        let set_past_neighbors_params = set_past_neighbors_params_arc.lock().unwrap();
+        assert!(set_past_neighbors_params.is_empty());
+    }
+
+    #[test]
+    fn neighborhood_does_not_updates_past_neighbors_without_password_even_when_neighbor_list_changes(
+    ) {
+        let subject_node = make_global_cryptde_node_record(5555, true); // 9e7p7un06eHs6frl5A
+        let old_neighbor = make_node_record(1111, true);
+        let new_neighbor = make_node_record(2222, true);
+        let mut subject: Neighborhood = neighborhood_from_nodes(&subject_node, Some(&old_neighbor));
+        subject
+            .neighborhood_database
+            .add_node(old_neighbor.clone())
+            .unwrap();
+        subject
+            .neighborhood_database
+            .add_arbitrary_full_neighbor(subject_node.public_key(), old_neighbor.public_key());
+        let gossip_acceptor = NeighborReplacementGossipAcceptor {
+            new_neighbors: vec![old_neighbor.clone(), new_neighbor.clone()],
         };
+        let set_past_neighbors_params_arc = Arc::new(Mutex::new(vec![]));
+        let persistent_config = PersistentConfigurationMock::new()
+            .set_past_neighbors_params(&set_past_neighbors_params_arc);
+        subject.gossip_acceptor = Box::new(gossip_acceptor);
+        subject.persistent_config_opt = Some(Box::new(persistent_config));
+        subject.db_password_opt = None;
 
-        assert_eq!(return_route_id_0, 0);
-        assert_eq!(return_route_id_1, 1);
+        subject.handle_gossip_agrs(vec![], SocketAddr::from_str("1.2.3.4:1234").unwrap());
+
+        let set_past_neighbors_params = set_past_neighbors_params_arc.lock().unwrap();
+        assert!(set_past_neighbors_params.is_empty());
     }
 
-    /*
-            Database:
+    #[test]
+    fn neighborhood_logs_error_when_past_neighbors_update_fails() {
+        init_test_logging();
+        let subject_node = make_global_cryptde_node_record(5555, true); // 9e7p7un06eHs6frl5A
+        let old_neighbor = make_node_record(1111, true);
+        let new_neighbor = make_node_record(2222, true);
+        let mut subject: Neighborhood = neighborhood_from_nodes(&subject_node, Some(&old_neighbor));
+        subject
+            .neighborhood_database
+            .add_node(old_neighbor.clone())
+            .unwrap();
+        subject
+            .neighborhood_database
+            .add_arbitrary_full_neighbor(subject_node.public_key(), old_neighbor.public_key());
+        let gossip_acceptor = NeighborReplacementGossipAcceptor {
+            new_neighbors: vec![old_neighbor.clone(), new_neighbor.clone()],
+        };
+        let persistent_config = PersistentConfigurationMock::new().set_past_neighbors_result(Err(
+            PersistentConfigError::DatabaseError("Booga".to_string()),
+        ));
+        subject.gossip_acceptor = Box::new(gossip_acceptor);
+        subject.persistent_config_opt = Some(Box::new(persistent_config));
 
-                 P---Q---R---S
-                     |
-                     T
+        subject.handle_gossip_agrs(vec![], SocketAddr::from_str("1.2.3.4:1234").unwrap());
 
-            Tests will be written from the viewpoint of P.
-    */
+        TestLogHandler::new().exists_log_containing("ERROR: Neighborhood: Could not persist immediate-neighbor changes: DatabaseError(\"Booga\")");
+    }
 
     #[test]
-    fn route_query_messages() {
-        let cryptde = main_cryptde();
-        let earning_wallet = make_wallet("earning");
-        let system = System::new("route_query_messages");
-        let mut subject = make_standard_subject();
+    fn neighborhood_sends_from_gossip_producer_when_acceptance_introductions_are_not_provided() {
+        init_test_logging();
+        let subject_node = make_global_cryptde_node_record(5555, true); // 9e7p7un06eHs6frl5A
+        let neighbor = make_node_record(1050, true);
+        let mut subject = neighborhood_from_nodes(&subject_node, Some(&neighbor));
+        let full_neighbor = make_node_record(1234, true);
+        let half_neighbor = make_node_record(2345, true);
         subject
             .neighborhood_database
-            .root_mut()
-            .set_earning_wallet(earning_wallet);
-        let consuming_wallet_opt = subject.consuming_wallet_opt.clone();
-        let p = &subject.neighborhood_database.root().clone();
-        let q = &make_node_record(3456, true);
-        let r = &make_node_record(4567, false);
-        let s = &make_node_record(5678, false);
-        let mut t = make_node_record(1111, false);
-        t.set_desirable(false);
-        {
-            let db = &mut subject.neighborhood_database;
-            db.add_node(q.clone()).unwrap();
-            db.add_node(t.clone()).unwrap();
-            db.add_node(r.clone()).unwrap();
-            db.add_node(s.clone()).unwrap();
-            let mut dual_edge = |a: &NodeRecord, b: &NodeRecord| {
-                db.add_arbitrary_full_neighbor(a.public_key(), b.public_key());
-            };
-            dual_edge(p, q);
-            dual_edge(q, &t);
-            dual_edge(q, r);
-            dual_edge(r, s);
-        }
+            .add_node(full_neighbor.clone())
+            .unwrap();
+        subject
+            .neighborhood_database
+            .add_node(half_neighbor.clone())
+            .unwrap();
+        subject
+            .neighborhood_database
+            .add_arbitrary_full_neighbor(subject_node.public_key(), full_neighbor.public_key());
+        subject
+            .neighborhood_database
+            .add_arbitrary_half_neighbor(subject_node.public_key(), half_neighbor.public_key());
+        let gossip_acceptor =
+            GossipAcceptorMock::new().handle_result(GossipAcceptanceResult::Accepted);
+        subject.gossip_acceptor = Box::new(gossip_acceptor);
+        let gossip = Gossip_0v1::new(vec![]);
+        let produce_params_arc = Arc::new(Mutex::new(vec![]));
+        let gossip_producer = GossipProducerMock::new()
+            .produce_params(&produce_params_arc)
+            .produce_result(Some(gossip.clone()))
+            .produce_result(Some(gossip.clone()));
+        subject.gossip_producer = Box::new(gossip_producer);
+        let (hopper, _, hopper_recording_arc) = make_recorder();
+        let peer_actors = peer_actors_builder().hopper(hopper).build();
 
-        let addr: Addr<Neighborhood> = subject.start();
-        let sub: Recipient<RouteQueryMessage> = addr.recipient::<RouteQueryMessage>();
+        let system = System::new("");
+        subject.hopper = Some(peer_actors.hopper.from_hopper_client);
 
-        let data_route = sub.send(RouteQueryMessage::data_indefinite_route_request(2));
+        subject.handle_gossip(
+            Gossip_0v1::new(vec![]),
+            SocketAddr::from_str("1.1.1.1:1111").unwrap(),
+        );
 
-        System::current().stop_with_code(0);
+        System::current().stop();
         system.run();
 
-        let result = data_route.wait().unwrap().unwrap();
-        let contract_address = contract_address(DEFAULT_CHAIN_ID);
-        let expected_response = RouteQueryResponse {
-            route: Route::round_trip(
-                segment(&[p, q, r], &Component::ProxyClient),
-                segment(&[r, q, p], &Component::ProxyServer),
-                cryptde,
-                consuming_wallet_opt,
-                0,
-                Some(contract_address),
+        let hopper_recording = hopper_recording_arc.lock().unwrap();
+        let package_1 = hopper_recording.get_record::<IncipientCoresPackage>(0);
+        let package_2 = hopper_recording.get_record::<IncipientCoresPackage>(1);
+        assert_eq!(hopper_recording.len(), 2);
+        fn digest(package: IncipientCoresPackage) -> (PublicKey, CryptData) {
+            (
+                package.route.next_hop(main_cryptde()).unwrap().public_key,
+                package.payload,
             )
-            .unwrap(),
-            expected_services: ExpectedServices::RoundTrip(
-                vec![
-                    ExpectedService::Nothing,
-                    ExpectedService::Routing(
-                        q.public_key().clone(),
-                        q.earning_wallet(),
-                        rate_pack(3456),
-                    ),
-                    ExpectedService::Exit(
-                        r.public_key().clone(),
-                        r.earning_wallet(),
-                        rate_pack(4567),
-                    ),
-                ],
-                vec![
-                    ExpectedService::Exit(
-                        r.public_key().clone(),
-                        r.earning_wallet(),
-                        rate_pack(4567),
-                    ),
-                    ExpectedService::Routing(
-                        q.public_key().clone(),
-                        q.earning_wallet(),
-                        rate_pack(3456),
-                    ),
-                    ExpectedService::Nothing,
-                ],
-                0,
-            ),
-        };
-        assert_eq!(expected_response, result);
+        }
+        let digest_set = vec_to_set(vec![digest(package_1.clone()), digest(package_2.clone())]);
+        assert_eq!(
+            vec_to_set(vec![
+                (
+                    full_neighbor.public_key().clone(),
+                    encodex(
+                        main_cryptde(),
+                        full_neighbor.public_key(),
+                        &MessageType::Gossip(gossip.clone().into()),
+                    )
+                    .unwrap()
+                ),
+                (
+                    half_neighbor.public_key().clone(),
+                    encodex(
+                        main_cryptde(),
+                        half_neighbor.public_key(),
+                        &MessageType::Gossip(gossip.into()),
+                    )
+                    .unwrap()
+                ),
+            ]),
+            digest_set
+        );
+        let tlh = TestLogHandler::new();
+        tlh.exists_log_containing(
+            format!(
+                "INFO: Neighborhood: Sending update Gossip about 0 Nodes to Node {}",
+                full_neighbor.public_key()
+            )
+            .as_str(),
+        );
+        tlh.exists_log_containing(
+            format!(
+                "INFO: Neighborhood: Sending update Gossip about 0 Nodes to Node {}",
+                half_neighbor.public_key()
+            )
+            .as_str(),
+        );
+        let key_as_str = format!("{}", main_cryptde().public_key());
+        tlh.exists_log_containing(&format!("Sent Gossip: digraph db {{ \"src\" [label=\"Gossip From:\\n{}\\n5.5.5.5\"]; \"dest\" [label=\"Gossip To:\\nAQIDBA\\n1.2.3.4\"]; \"src\" -> \"dest\" [arrowhead=empty]; }}", &key_as_str[..8]));
+        tlh.exists_log_containing(&format!("Sent Gossip: digraph db {{ \"src\" [label=\"Gossip From:\\n{}\\n5.5.5.5\"]; \"dest\" [label=\"Gossip To:\\nAgMEBQ\\n2.3.4.5\"]; \"src\" -> \"dest\" [arrowhead=empty]; }}", &key_as_str[..8]));
     }
 
     #[test]
-    fn sort_routes_by_desirable_exit_nodes() {
-        let mut subject = make_standard_subject();
-
-        let us = subject.neighborhood_database.root().clone();
-        let routing_node = make_node_record(0000, true);
-        let desirable_node = make_node_record(1111, false);
-        let mut undesirable_node = make_node_record(2222, false);
-        undesirable_node.set_desirable(false);
-
-        subject
-            .neighborhood_database
-            .add_node(routing_node.clone())
-            .unwrap();
+    fn neighborhood_sends_no_gossip_when_target_does_not_exist() {
+        let subject_node = make_global_cryptde_node_record(5555, true); // 9e7p7un06eHs6frl5A
+                                                                        // This is ungossippable not because of any attribute of its own, but because the
+                                                                        // GossipProducerMock is set to return None when ordered to target it.
+        let ungossippable = make_node_record(1050, true);
+        let mut subject = neighborhood_from_nodes(&subject_node, Some(&ungossippable));
         subject
             .neighborhood_database
-            .add_node(undesirable_node.clone())
+            .add_node(ungossippable.clone())
             .unwrap();
         subject
             .neighborhood_database
-            .add_node(desirable_node.clone())
-            .unwrap();
-
-        let mut node_sequences = Vec::new();
-        node_sequences.push(vec![
-            us.public_key(),
-            routing_node.public_key(),
-            undesirable_node.public_key(),
-        ]);
-        node_sequences.push(vec![
-            us.public_key(),
-            routing_node.public_key(),
-            desirable_node.public_key(),
-        ]);
+            .add_arbitrary_full_neighbor(subject_node.public_key(), ungossippable.public_key());
+        let gossip_acceptor =
+            GossipAcceptorMock::new().handle_result(GossipAcceptanceResult::Accepted);
+        subject.gossip_acceptor = Box::new(gossip_acceptor);
+        let produce_params_arc = Arc::new(Mutex::new(vec![]));
+        let gossip_producer = GossipProducerMock::new()
+            .produce_params(&produce_params_arc)
+            .produce_result(None);
+        subject.gossip_producer = Box::new(gossip_producer);
+        let (hopper, _, hopper_recording_arc) = make_recorder();
+        let peer_actors = peer_actors_builder().hopper(hopper).build();
 
-        subject.sort_routes_by_desirable_exit_nodes(&mut node_sequences);
+        let system = System::new("");
+        subject.hopper = Some(peer_actors.hopper.from_hopper_client);
 
-        assert_eq!(desirable_node.public_key(), node_sequences[0][2]);
-        assert_eq!(undesirable_node.public_key(), node_sequences[1][2]);
-    }
+        subject.handle_gossip(
+            Gossip_0v1::new(vec![]),
+            SocketAddr::from_str("1.1.1.1:1111").unwrap(),
+        );
 
-    #[test]
-    #[should_panic(expected = "Unable to sort routes by desirable exit nodes: Missing routes.")]
-    fn sort_routes_by_desirable_exit_nodes_panics_with_empty_node_sequences() {
-        let subject = make_standard_subject();
+        System::current().stop();
+        system.run();
 
-        let mut node_sequences: Vec<Vec<&PublicKey>> = Vec::new();
-        subject.sort_routes_by_desirable_exit_nodes(&mut node_sequences);
+        let hopper_recording = hopper_recording_arc.lock().unwrap();
+        assert_eq!(hopper_recording.len(), 0);
     }
 
     #[test]
-    #[should_panic(
-        expected = "Unable to sort routes by desirable exit nodes: Missing route segments."
-    )]
-    fn sort_routes_by_desirable_exit_nodes_panics_with_the_first_route_segment_empty() {
-        let subject = make_standard_subject();
+    fn neighborhood_sends_only_relay_gossip_when_gossip_acceptor_relays() {
+        let subject_node = make_global_cryptde_node_record(5555, true); // 9e7p7un06eHs6frl5A
+        let mut subject =
+            neighborhood_from_nodes(&subject_node, Some(&make_node_record(1111, true)));
+        let debut_node = make_node_record(1234, true);
+        let debut_gossip = GossipBuilder::new(&subject.neighborhood_database)
+            .node(subject_node.public_key(), true)
+            .build();
+        let gossip_acceptor =
+            GossipAcceptorMock::new().handle_result(GossipAcceptanceResult::Reply(
+                debut_gossip.clone(),
+                debut_node.public_key().clone(),
+                debut_node.node_addr_opt().unwrap(),
+            ));
+        subject.gossip_acceptor = Box::new(gossip_acceptor);
+        let (hopper, _, hopper_recording_arc) = make_recorder();
+        let peer_actors = peer_actors_builder().hopper(hopper).build();
+        let system = System::new("");
+        subject.hopper_no_lookup = Some(peer_actors.hopper.from_hopper_client_no_lookup);
+        let gossip_source = SocketAddr::from_str("8.6.5.4:8654").unwrap();
 
-        let mut node_sequences: Vec<Vec<&PublicKey>> = Vec::new();
-        let public_key = &PublicKey::from(&b"1234"[..]);
-        node_sequences.push(vec![]);
-        node_sequences.push(vec![public_key]);
+        subject.handle_gossip(
+            // In real life this would be Relay Gossip from gossip_source to debut_node.
+            Gossip_0v1::new(vec![]),
+            gossip_source,
+        );
 
-        subject.sort_routes_by_desirable_exit_nodes(&mut node_sequences);
+        System::current().stop();
+        system.run();
+        let hopper_recording = hopper_recording_arc.lock().unwrap();
+        let package = hopper_recording.get_record::<NoLookupIncipientCoresPackage>(0);
+        assert_eq!(1, hopper_recording.len());
+        assert_eq!(debut_node.public_key(), &package.public_key);
+        assert_eq!(
+            debut_node.node_addr_opt().as_ref().unwrap(),
+            &package.node_addr
+        );
+        assert_eq!(
+            debut_gossip,
+            match decodex::<MessageType>(
+                &CryptDENull::from(debut_node.public_key(), DEFAULT_CHAIN_ID),
+                &package.payload,
+            ) {
+                Ok(MessageType::Gossip(vd)) => Gossip_0v1::try_from(vd).unwrap(),
+                x => panic!("Expected Gossip, but found {:?}", x),
+            },
+        );
     }
 
     #[test]
-    #[should_panic(
-        expected = "Unable to sort routes by desirable exit nodes: Missing route segments."
-    )]
-    fn sort_routes_by_desirable_exit_nodes_panics_with_the_second_route_segment_empty() {
-        let subject = make_standard_subject();
+    fn neighborhood_sends_no_gossip_when_gossip_acceptor_ignores() {
+        let subject_node = make_global_cryptde_node_record(5555, true); // 9e7p7un06eHs6frl5A
+        let neighbor = make_node_record(1111, true);
+        let mut subject = neighborhood_from_nodes(&subject_node, Some(&neighbor));
+        let gossip_acceptor =
+            GossipAcceptorMock::new().handle_result(GossipAcceptanceResult::Ignored);
+        subject.gossip_acceptor = Box::new(gossip_acceptor);
+        let subject_node = subject.neighborhood_database.root().clone();
+        let (hopper, _, hopper_recording_arc) = make_recorder();
+        let peer_actors = peer_actors_builder().hopper(hopper).build();
+        let system = System::new("");
+        subject.hopper = Some(peer_actors.hopper.from_hopper_client);
 
-        let mut node_sequences: Vec<Vec<&PublicKey>> = Vec::new();
-        let public_key = &PublicKey::from(&b"1234"[..]);
-        node_sequences.push(vec![public_key]);
-        node_sequences.push(vec![]);
+        subject.handle_gossip(
+            Gossip_0v1::new(vec![]),
+            subject_node.node_addr_opt().unwrap().into(),
+        );
 
-        subject.sort_routes_by_desirable_exit_nodes(&mut node_sequences);
+        System::current().stop();
+        system.run();
+        let hopper_recording = hopper_recording_arc.lock().unwrap();
+        assert_eq!(0, hopper_recording.len());
     }
 
     #[test]
-    #[should_panic(
-        expected = "Unable to sort routes by desirable exit nodes: Missing NodeRecord for public key: [MTIzNA]"
-    )]
-    fn sort_routes_by_desirable_exit_nodes_panics_when_node_record_is_missing() {
-        let subject = make_standard_subject();
+    fn neighborhood_ignores_malefactor_gossip_when_gossip_acceptor_requests_a_ban() {
+        init_test_logging();
+        let subject_node = make_global_cryptde_node_record(5555, true); // 9e7p7un06eHs6frl5A
+        let neighbor = make_node_record(1111, true);
+        let mut subject = neighborhood_from_nodes(&subject_node, Some(&neighbor));
+        let gossip_acceptor = GossipAcceptorMock::new()
+            .handle_result(GossipAcceptanceResult::Ban("Bad guy".to_string()));
+        subject.gossip_acceptor = Box::new(gossip_acceptor);
+        let subject_node = subject.neighborhood_database.root().clone();
+        let (hopper, _, hopper_recording_arc) = make_recorder();
+        let peer_actors = peer_actors_builder().hopper(hopper).build();
+        let system = System::new("");
+        subject.hopper = Some(peer_actors.hopper.from_hopper_client);
 
-        let mut node_sequences: Vec<Vec<&PublicKey>> = Vec::new();
-        let public_key = &PublicKey::from(&b"1234"[..]);
-        node_sequences.push(vec![public_key]);
-        node_sequences.push(vec![public_key]);
-        println!("{}", public_key);
+        subject.handle_gossip(
+            Gossip_0v1::new(vec![]),
+            subject_node.node_addr_opt().unwrap().into(),
+        );
 
-        subject.sort_routes_by_desirable_exit_nodes(&mut node_sequences);
+        System::current().stop();
+        system.run();
+        let hopper_recording = hopper_recording_arc.lock().unwrap();
+        assert_eq!(0, hopper_recording.len());
+        let tlh = TestLogHandler::new();
+        tlh.exists_log_containing("WARN: Neighborhood: Malefactor detected at 5.5.5.5:5555; counting it against its trust and ignoring: Bad guy");
     }
 
     #[test]
-    fn compose_route_query_response_returns_an_error_when_route_segment_is_empty() {
+    fn gossip_is_weighed_by_the_trust_of_its_sources_earning_wallet() {
+        init_test_logging();
+        let home_dir = ensure_node_home_directory_exists(
+            "neighborhood",
+            "gossip_is_weighed_by_the_trust_of_its_sources_earning_wallet",
+        );
         let mut subject = make_standard_subject();
-
-        let result: Result<RouteQueryResponse, String> = subject.compose_route_query_response(
-            RouteSegment::new(vec![], Component::Neighborhood),
-            RouteSegment::new(vec![], Component::Neighborhood),
+        subject.trust_dao_opt = Some(Box::new(TrustDaoReal::new(
+            DbInitializerReal::new()
+                .initialize(&home_dir, DEFAULT_CHAIN_ID, true)
+                .unwrap(),
+        )));
+        let mut malefactor = make_node_record(4567, true);
+        malefactor.set_earning_wallet(make_wallet("gossip malefactor"));
+        let mut distrusted = make_node_record(5678, true);
+        distrusted.set_earning_wallet(make_wallet("distrusted gossiper"));
+        TRUST_CACHE.insert(
+            &distrusted.earning_wallet(),
+            TrustRecord {
+                payments_overdue: 10,
+                inaccurate_gossip: 10,
+                ..TrustRecord::default()
+            },
         );
-        assert!(result.is_err());
-        let error_expectation: String = result.expect_err("Expected an Err but got:");
+        subject
+            .neighborhood_database
+            .add_node(malefactor.clone())
+            .unwrap();
+        subject
+            .neighborhood_database
+            .add_node(distrusted.clone())
+            .unwrap();
+        let handle_params_arc = Arc::new(Mutex::new(vec![]));
+        subject.gossip_acceptor = Box::new(
+            GossipAcceptorMock::new()
+                .handle_params(&handle_params_arc)
+                .handle_result(GossipAcceptanceResult::Ban("Bad guy".to_string())),
+        );
+        let distrusted_addr: SocketAddr = distrusted.node_addr_opt().unwrap().into();
+
+        subject.handle_agrs(vec![], malefactor.node_addr_opt().unwrap().into());
+        subject.handle_agrs(vec![], distrusted_addr);
+
+        assert_eq!(handle_params_arc.lock().unwrap().len(), 1);
         assert_eq!(
-            error_expectation,
-            "Cannot make multi-hop route without segment keys"
+            TRUST_CACHE
+                .record(&malefactor.earning_wallet())
+                .unwrap()
+                .inaccurate_gossip,
+            1
         );
+        TestLogHandler::new().exists_log_containing(&format!(
+            "INFO: Neighborhood: Gossip from {} ignored: its earning wallet's trust score is 12",
+            distrusted_addr
+        ));
     }
 
     #[test]
-    fn next_return_route_id_wraps_around() {
+    fn neighborhood_does_not_accept_gossip_if_a_record_is_non_deserializable() {
+        init_test_logging();
         let mut subject = make_standard_subject();
-        subject.next_return_route_id = 0xFFFFFFFF;
+        let gossip_acceptor = GossipAcceptorMock::new();
+        subject.gossip_acceptor = Box::new(gossip_acceptor);
+        let db = &mut subject.neighborhood_database;
+        let one_node_key = &db.add_node(make_node_record(2222, true)).unwrap();
+        let another_node_key = &db.add_node(make_node_record(3333, true)).unwrap();
+        let mut gossip = GossipBuilder::new(db)
+            .node(one_node_key, true)
+            .node(another_node_key, false)
+            .build();
+        gossip.node_records[1].signed_data = PlainData::new(&[1, 2, 3, 4]); // corrupt second record
+        let gossip_source = SocketAddr::from_str("1.2.3.4:1234").unwrap();
 
-        let end = subject.advance_return_route_id();
-        let beginning = subject.advance_return_route_id();
+        subject.handle_gossip(gossip, gossip_source);
 
-        assert_eq!(end, 0xFFFFFFFF);
-        assert_eq!(beginning, 0x00000000);
+        // No panic means that subject didn't try to invoke the GossipAcceptorMock: test passes!
+        TestLogHandler::new().exists_log_containing(&format!(
+            "ERROR: Neighborhood: Received non-deserializable Gossip from {}",
+            gossip_source
+        ));
     }
 
-    /*
-            Database:
-
-                 O---R---E
-
-            Tests will be written from the viewpoint of O.
-    */
-
     #[test]
-    fn return_route_ids_increase() {
-        let cryptde = main_cryptde();
-        let system = System::new("return_route_ids_increase");
-        let (_, _, _, subject) = make_o_r_e_subject();
-
-        let addr: Addr<Neighborhood> = subject.start();
-        let sub: Recipient<RouteQueryMessage> = addr.recipient::<RouteQueryMessage>();
-
-        let data_route_0 = sub.send(RouteQueryMessage::data_indefinite_route_request(2));
-        let data_route_1 = sub.send(RouteQueryMessage::data_indefinite_route_request(2));
+    fn neighborhood_does_not_accept_gossip_if_a_record_signature_is_invalid() {
+        init_test_logging();
+        let mut subject = make_standard_subject();
+        let gossip_acceptor = GossipAcceptorMock::new();
+        subject.gossip_acceptor = Box::new(gossip_acceptor);
+        let db = &mut subject.neighborhood_database;
+        let one_node_key = &db.add_node(make_node_record(2222, true)).unwrap();
+        let another_node_key = &db.add_node(make_node_record(3333, true)).unwrap();
+        let mut gossip = GossipBuilder::new(db)
+            .node(one_node_key, true)
+            .node(another_node_key, false)
+            .build();
+        gossip.node_records[1].signature = CryptData::new(&[1, 2, 3, 4]); // corrupt second record
+        let gossip_source = SocketAddr::from_str("1.2.3.4:1234").unwrap();
 
-        System::current().stop_with_code(0);
-        system.run();
+        subject.handle_gossip(gossip, gossip_source);
 
-        let result_0 = data_route_0.wait().unwrap().unwrap();
-        let result_1 = data_route_1.wait().unwrap().unwrap();
-        let juicy_parts = |result: RouteQueryResponse| {
-            let last_element = result.route.hops.last().unwrap();
-            let last_element_dec = cryptde.decode(last_element).unwrap();
-            let network_return_route_id: u32 =
-                serde_cbor::de::from_slice(last_element_dec.as_slice()).unwrap();
-            let metadata_return_route_id = match result.expected_services {
-                ExpectedServices::RoundTrip(_, _, id) => id,
-                _ => panic!("expected RoundTrip got OneWay"),
-            };
-            (network_return_route_id, metadata_return_route_id)
-        };
-        assert_eq!(juicy_parts(result_0), (0, 0));
-        assert_eq!(juicy_parts(result_1), (1, 1));
+        // No panic means that subject didn't try to invoke the GossipAcceptorMock: test passes!
+        TestLogHandler::new().exists_log_containing(&format!(
+            "ERROR: Neighborhood: Received Gossip with invalid signature from {}",
+            gossip_source
+        ));
     }
 
     #[test]
-    fn can_update_consuming_wallet() {
-        let cryptde = main_cryptde();
-        let system = System::new("can_update_consuming_wallet");
-        let (o, r, e, subject) = make_o_r_e_subject();
-        let addr: Addr<Neighborhood> = subject.start();
-        let set_wallet_sub = addr.clone().recipient::<SetConsumingWalletMessage>();
-        let route_sub = addr.recipient::<RouteQueryMessage>();
-        let expected_new_wallet = make_paying_wallet(b"new consuming wallet");
-        let expected_before_route = Route::round_trip(
-            segment(&[&o, &r, &e], &Component::ProxyClient),
-            segment(&[&e, &r, &o], &Component::ProxyServer),
-            cryptde,
-            Some(make_paying_wallet(b"consuming")),
-            0,
-            Some(contract_address(DEFAULT_CHAIN_ID)),
-        )
-        .unwrap();
-        let expected_after_route = Route::round_trip(
-            segment(&[&o, &r, &e], &Component::ProxyClient),
-            segment(&[&e, &r, &o], &Component::ProxyServer),
-            cryptde,
-            Some(expected_new_wallet.clone()),
-            1,
-            Some(contract_address(DEFAULT_CHAIN_ID)),
-        )
-        .unwrap();
-
-        let route_request_1 = route_sub.send(RouteQueryMessage::data_indefinite_route_request(2));
-        let _ = set_wallet_sub.try_send(SetConsumingWalletMessage {
-            wallet: expected_new_wallet,
-        });
-        let route_request_2 = route_sub.send(RouteQueryMessage::data_indefinite_route_request(2));
-
-        System::current().stop();
-        system.run();
+    fn neighborhood_does_not_accept_gossip_if_a_node_addr_signature_is_invalid() {
+        init_test_logging();
+        let mut subject = make_standard_subject();
+        let gossip_acceptor = GossipAcceptorMock::new();
+        subject.gossip_acceptor = Box::new(gossip_acceptor);
+        let db = &mut subject.neighborhood_database;
+        let one_node_key = &db.add_node(make_node_record(2222, true)).unwrap();
+        let another_node_key = &db.add_node(make_node_record(3333, true)).unwrap();
+        let mut gossip = GossipBuilder::new(db)
+            .node(one_node_key, true)
+            .node(another_node_key, true)
+            .build();
+        gossip.node_records[1].node_addr_opt = Some(NodeAddr::new(
+            &IpAddr::from_str("6.6.6.6").unwrap(),
+            &[6666],
+        )); // relocate second record without re-signing
+        let gossip_source = SocketAddr::from_str("2.2.2.2:2222").unwrap();
 
-        let route_1 = route_request_1.wait().unwrap().unwrap().route;
-        let route_2 = route_request_2.wait().unwrap().unwrap().route;
+        subject.handle_gossip(gossip, gossip_source);
 
-        assert_eq!(route_1, expected_before_route);
-        assert_eq!(route_2, expected_after_route);
+        // No panic means that subject didn't try to invoke the GossipAcceptorMock: test passes!
+        TestLogHandler::new().exists_log_containing(&format!(
+            "ERROR: Neighborhood: Received Gossip with invalid NodeAddr signature from {}",
+            gossip_source
+        ));
     }
 
     #[test]
-    fn compose_route_query_response_returns_an_error_when_route_segment_keys_is_empty() {
+    fn neighborhood_discards_unsigned_node_addrs_that_do_not_belong_to_the_gossip_source() {
+        let handle_params_arc = Arc::new(Mutex::new(vec![]));
         let mut subject = make_standard_subject();
+        let gossip_acceptor = GossipAcceptorMock::new()
+            .handle_params(&handle_params_arc)
+            .handle_result(GossipAcceptanceResult::Ignored);
+        subject.gossip_acceptor = Box::new(gossip_acceptor);
+        let db = &mut subject.neighborhood_database;
+        let source_key = &db.add_node(make_node_record(2222, true)).unwrap();
+        let third_party_key = &db.add_node(make_node_record(3333, true)).unwrap();
+        let signed_key = &db.add_node(make_node_record(4444, true)).unwrap();
+        let mut gossip = GossipBuilder::new(db)
+            .node(source_key, true)
+            .node(third_party_key, true)
+            .node(signed_key, true)
+            .build();
+        gossip.node_records[0].node_addr_signature_opt = None;
+        gossip.node_records[1].node_addr_signature_opt = None;
+        let gossip_source = SocketAddr::from_str("2.2.2.2:2222").unwrap();
 
-        let result: Result<RouteQueryResponse, String> = subject.compose_route_query_response(
-            RouteSegment::new(vec![], Component::ProxyClient),
-            RouteSegment::new(vec![], Component::ProxyServer),
+        subject.handle_gossip(gossip, gossip_source);
+
+        let mut handle_params = handle_params_arc.lock().unwrap();
+        let (_, agrs, _) = handle_params.remove(0);
+        assert_eq!(
+            agrs[0].node_addr_opt,
+            Some(NodeAddr::new(
+                &IpAddr::from_str("2.2.2.2").unwrap(),
+                &[2222]
+            ))
         );
-        assert!(result.is_err());
-        let error_expectation: String = result.expect_err("Expected an Err but got:");
+        assert_eq!(agrs[1].node_addr_opt, None);
         assert_eq!(
-            error_expectation,
-            "Cannot make multi-hop route without segment keys"
+            agrs[2].node_addr_opt,
+            Some(NodeAddr::new(
+                &IpAddr::from_str("4.4.4.4").unwrap(),
+                &[4444]
+            ))
         );
     }
 
     #[test]
-    fn compose_route_query_response_returns_an_error_when_the_neighbor_is_none() {
-        let mut subject = make_standard_subject();
+    fn node_addr_signature_status_is_computed_correctly() {
+        let cryptde = CryptDENull::from(&PublicKey::new(&[2, 2, 2, 2]), DEFAULT_CHAIN_ID);
+        let node = make_node_record(2222, true);
+        let signed = AccessibleGossipRecord::from(&node);
+        let mut unsigned = signed.clone();
+        unsigned.node_addr_signature_opt = None;
+        let mut forged = signed.clone();
+        forged.node_addr_opt = Some(NodeAddr::new(
+            &IpAddr::from_str("6.6.6.6").unwrap(),
+            &[2222],
+        ));
+        let mut addressless = signed.clone();
+        addressless.node_addr_opt = None;
 
-        let result: Result<RouteQueryResponse, String> = subject.compose_route_query_response(
-            RouteSegment::new(vec![&PublicKey::new(&[3, 3, 8])], Component::ProxyClient),
-            RouteSegment::new(vec![&PublicKey::new(&[8, 3, 3])], Component::ProxyServer),
+        assert_eq!(
+            signed.node_addr_signature_status(&cryptde),
+            NodeAddrSignatureStatus::Valid
         );
-        assert!(result.is_err());
-        let error_expectation: String = result.expect_err("Expected an Err but got:");
         assert_eq!(
-            error_expectation,
-            "Cannot make multi_hop with unknown neighbor"
+            unsigned.node_addr_signature_status(&cryptde),
+            NodeAddrSignatureStatus::Unsigned
+        );
+        assert_eq!(
+            forged.node_addr_signature_status(&cryptde),
+            NodeAddrSignatureStatus::Invalid
+        );
+        assert_eq!(
+            addressless.node_addr_signature_status(&cryptde),
+            NodeAddrSignatureStatus::NoNodeAddr
         );
-        assert_eq!(subject.next_return_route_id, 0);
     }
 
     #[test]
-    fn calculate_expected_service_returns_error_when_given_empty_segment() {
-        let mut subject = make_standard_subject();
-        let a = &make_node_record(3456, true);
-        let db = &mut subject.neighborhood_database;
-        db.add_node(a.clone()).unwrap();
+    fn a_node_addr_signature_from_an_earlier_version_is_not_accepted_in_a_later_one() {
+        let cryptde = CryptDENull::from(&PublicKey::new(&[2, 2, 2, 2]), DEFAULT_CHAIN_ID);
+        let mut node = make_node_record(2222, true);
+        let earlier = AccessibleGossipRecord::from(&node);
+        node.increment_version();
+        node.force_node_addr(&NodeAddr::new(
+            &IpAddr::from_str("7.7.7.7").unwrap(),
+            &[7777],
+        ));
+        node.regenerate_signed_gossip(&cryptde);
+        let later = AccessibleGossipRecord::from(&node);
+        let mut replayed = later.clone();
+        replayed.node_addr_opt = earlier.node_addr_opt.clone();
+        replayed.node_addr_signature_opt = earlier.node_addr_signature_opt.clone();
 
-        let result = subject.calculate_expected_service(a.public_key(), None, None);
-        assert!(result.is_err());
         assert_eq!(
-            result.unwrap_err(),
-            "cannot calculate expected service, no keys provided in route segment"
+            earlier.node_addr_signature_status(&cryptde),
+            NodeAddrSignatureStatus::Valid
+        );
+        assert_eq!(
+            later.node_addr_signature_status(&cryptde),
+            NodeAddrSignatureStatus::Valid
+        );
+        assert_eq!(
+            replayed.node_addr_signature_status(&cryptde),
+            NodeAddrSignatureStatus::Invalid
         );
     }
 
-    /*
-            Database:
+    #[test]
+    fn neighborhood_logs_received_gossip_in_dot_graph_format() {
+        init_test_logging();
+        let cryptde = main_cryptde();
+        let this_node = NodeRecord::new_for_tests(
+            &cryptde.public_key(),
+            Some(&NodeAddr::new(
+                &IpAddr::from_str("5.4.3.2").unwrap(),
+                &[1234],
+            )),
+            100,
+            true,
+            true,
+        );
+        let mut db = db_from_node(&this_node);
+        let far_neighbor = make_node_record(1324, true);
+        let gossip_neighbor = make_node_record(4657, true);
+        db.add_node(far_neighbor.clone()).unwrap();
+        db.add_node(gossip_neighbor.clone()).unwrap();
+        db.add_arbitrary_full_neighbor(this_node.public_key(), gossip_neighbor.public_key());
+        db.add_arbitrary_full_neighbor(gossip_neighbor.public_key(), far_neighbor.public_key());
+        db.node_by_key_mut(this_node.public_key()).unwrap().resign();
+        db.node_by_key_mut(gossip_neighbor.public_key())
+            .unwrap()
+            .resign();
+        db.node_by_key_mut(far_neighbor.public_key())
+            .unwrap()
+            .resign();
 
-            Q---p---R
-                |   |
-            t---S---+
+        let gossip = GossipBuilder::new(&db)
+            .node(gossip_neighbor.public_key(), true)
+            .node(far_neighbor.public_key(), false)
+            .build();
+        let cores_package = ExpiredCoresPackage {
+            immediate_neighbor: SocketAddr::from_str("1.2.3.4:1234").unwrap(),
+            paying_wallet: Some(make_paying_wallet(b"consuming")),
+            remaining_route: make_meaningless_route(),
+            payload: gossip,
+            payload_len: 0,
+        };
+        let hopper = Recorder::new();
+        let this_node_inside = this_node.clone();
+        thread::spawn(move || {
+            let system = System::new("");
+            let subject = Neighborhood::new(
+                cryptde,
+                &bc_from_nc_plus(
+                    NeighborhoodConfig {
+                        mode: NeighborhoodMode::Standard(
+                            this_node_inside.node_addr_opt().unwrap(),
+                            vec![],
+                            rate_pack(100),
+                        ),
+                    },
+                    this_node_inside.earning_wallet(),
+                    None,
+                    "neighborhood_logs_received_gossip_in_dot_graph_format",
+                ),
+            );
 
-            p is consume-only, t is originate-only.
-    */
+            let addr: Addr<Neighborhood> = subject.start();
+            let peer_actors = peer_actors_builder().hopper(hopper).build();
+            addr.try_send(BindMessage { peer_actors }).unwrap();
+
+            let sub = addr.recipient::<ExpiredCoresPackage<Gossip_0v1>>();
+            sub.try_send(cores_package).unwrap();
+
+            system.run();
+        });
+        let tlh = TestLogHandler::new();
+        tlh.await_log_containing(
+            &format!("\"BAYFBw\" [label=\"AR v0\\nBAYFBw\\n4.6.5.7:4657\"];"),
+            5000,
+        );
+
+        tlh.exists_log_containing("Received Gossip: digraph db { ");
+        tlh.exists_log_containing("\"AQMCBA\" [label=\"AR v0\\nAQMCBA\"];");
+        tlh.exists_log_containing(&format!(
+            "\"{}\" [label=\"{}\"] [shape=none];",
+            cryptde.public_key(),
+            &format!("{}", cryptde.public_key())[..8]
+        ));
+        tlh.exists_log_containing(&format!("\"BAYFBw\" -> \"{}\";", cryptde.public_key()));
+        tlh.exists_log_containing("\"AQMCBA\" -> \"BAYFBw\";");
+        tlh.exists_log_containing("\"BAYFBw\" -> \"AQMCBA\";");
+    }
 
     #[test]
-    fn complete_routes_exercise() {
-        let mut subject = make_standard_subject();
-        let db = &mut subject.neighborhood_database;
-        db.root_mut().inner.accepts_connections = false;
-        db.root_mut().inner.routes_data = false;
-        let p = &db.root_mut().public_key().clone(); // 9e7p7un06eHs6frl5A
-        let q = &db.add_node(make_node_record(3456, true)).unwrap(); // AwQFBg
-        let r = &db.add_node(make_node_record(4567, true)).unwrap(); // BAUGBw
-        let s = &db.add_node(make_node_record(5678, true)).unwrap(); // BQYHCA
-        let t = &db
-            .add_node(make_node_record_f(6789, true, false, true))
-            .unwrap(); // BgcICQ
-        db.add_arbitrary_full_neighbor(q, p);
-        db.add_arbitrary_full_neighbor(p, r);
-        db.add_arbitrary_full_neighbor(p, s);
-        db.add_arbitrary_full_neighbor(t, s);
-        db.add_arbitrary_full_neighbor(s, r);
+    fn node_gossips_to_neighbors_on_startup() {
+        let cryptde: &dyn CryptDE = main_cryptde();
+        let neighbor = make_node_record(1234, true);
+        let (hopper, _, hopper_recording) = make_recorder();
+        let neighbor_inside = neighbor.clone();
+        let subject = Neighborhood::new(
+            cryptde,
+            &bc_from_nc_plus(
+                NeighborhoodConfig {
+                    mode: NeighborhoodMode::Standard(
+                        NodeAddr::new(&IpAddr::from_str("5.4.3.2").unwrap(), &[1234]),
+                        vec![NodeDescriptor::from((
+                            &neighbor_inside,
+                            DEFAULT_CHAIN_ID == chain_id_from_name(DEFAULT_CHAIN_NAME),
+                            cryptde,
+                        ))],
+                        rate_pack(100),
+                    ),
+                },
+                NodeRecord::earning_wallet_from_key(&cryptde.public_key()),
+                NodeRecord::consuming_wallet_from_key(&cryptde.public_key()),
+                "node_gossips_to_neighbors_on_startup",
+            ),
+        );
+        let this_node = subject.neighborhood_database.root().clone();
+        let system = System::new("node_gossips_to_neighbors_on_startup");
+        let addr: Addr<Neighborhood> = subject.start();
+        let peer_actors = peer_actors_builder().hopper(hopper).build();
+        addr.try_send(BindMessage { peer_actors }).unwrap();
 
-        let contains = |routes: &Vec<Vec<&PublicKey>>, expected_keys: Vec<&PublicKey>| {
-            assert_contains(&routes, &expected_keys);
+        let sub = addr.recipient::<StartMessage>();
+
+        sub.try_send(StartMessage {}).unwrap();
+        System::current().stop();
+        system.run();
+        let locked_recording = hopper_recording.lock().unwrap();
+        let package_ref: &NoLookupIncipientCoresPackage = locked_recording.get_record(0);
+        let neighbor_node_cryptde = CryptDENull::from(neighbor.public_key(), DEFAULT_CHAIN_ID);
+        let decrypted_payload = neighbor_node_cryptde.decode(&package_ref.payload).unwrap();
+        let gossip = match serde_cbor::de::from_slice(decrypted_payload.as_slice()).unwrap() {
+            MessageType::Gossip(vd) => Gossip_0v1::try_from(vd).unwrap(),
+            x => panic!("Should have been MessageType::Gossip, but was {:?}", x),
         };
+        let temp_db = db_from_node(&this_node);
+        let expected_gnr = GossipNodeRecord::from((&temp_db, this_node.public_key(), true));
+        assert_contains(&gossip.node_records, &expected_gnr);
+        assert_eq!(1, gossip.node_records.len());
+    }
 
-        // At least two hops from p to anywhere standard
-        let routes = subject.complete_routes(vec![p], None, 2, RouteDirection::Over);
+    /*
+            Database, where we'll fail to make a three-hop route to C after removing A:
 
-        assert_eq!(routes, vec![vec![p, s, t]]);
-        // no [p, r, s] or [p, s, r] because s and r are both neighbors of p and can't exit for it
+                 NN--------+
+                 NN        |
+                 |         |
+                 v         v
+                 AA-->BB-->CC
+                 AA<--BB<--CC
 
-        // At least two hops over from p to t
-        let routes = subject.complete_routes(vec![p], Some(t), 2, RouteDirection::Over);
+                 after removing A as neighbor...
 
-        contains(&routes, vec![p, s, t]);
-        contains(&routes, vec![p, r, s, t]);
-        assert_eq!(2, routes.len());
+                 NN--------+
+                 NN        |
+                           |
+                           v
+                 AA-->BB-->CC
+                 AA<--BB<--CC
 
-        // At least two hops over from t to p
-        let routes = subject.complete_routes(vec![t], Some(p), 2, RouteDirection::Over);
+            Tests will be written from the viewpoint of N.
+    */
 
-        assert_eq!(routes, Vec::<Vec<&PublicKey>>::new());
-        // p is consume-only; can't be an exit Node.
+    #[test]
+    fn neighborhood_removes_neighbor_when_directed_to() {
+        let system = System::new("neighborhood_removes_neighbor_when_directed_to");
+        let hopper = Recorder::new();
+        let mut subject = make_standard_subject();
+        let n = &subject.neighborhood_database.root().clone();
+        let a = &make_node_record(3456, true);
+        let b = &make_node_record(4567, false);
+        let c = &make_node_record(5678, true);
+        {
+            let db = &mut subject.neighborhood_database;
+            db.add_node(a.clone()).unwrap();
+            db.add_node(b.clone()).unwrap();
+            db.add_node(c.clone()).unwrap();
+            let mut single_edge = |a: &NodeRecord, b: &NodeRecord| {
+                db.add_arbitrary_half_neighbor(a.public_key(), b.public_key())
+            };
+            single_edge(n, a);
+            single_edge(n, c);
+            single_edge(a, b);
+            single_edge(b, a);
+            single_edge(b, c);
+            single_edge(c, b);
+        }
 
-        // At least two hops back from t to p
-        let routes = subject.complete_routes(vec![t], Some(p), 2, RouteDirection::Back);
+        let addr: Addr<Neighborhood> = subject.start();
+        let peer_actors = peer_actors_builder().hopper(hopper).build();
+        addr.try_send(BindMessage { peer_actors }).unwrap();
 
-        contains(&routes, vec![t, s, p]);
-        contains(&routes, vec![t, s, r, p]);
-        assert_eq!(2, routes.len());
-        // p is consume-only, but it's the originating Node, so including it is okay
+        addr.try_send(RemoveNeighborMessage {
+            public_key: a.public_key().clone(),
+        })
+        .unwrap();
 
-        // At least two hops from p to Q - impossible
-        let routes = subject.complete_routes(vec![p], Some(q), 2, RouteDirection::Over);
+        let three_hop_route_request = RouteQueryMessage {
+            target_key_opt: Some(c.public_key().clone()),
+            target_component: Component::ProxyClient,
+            minimum_hop_count: 3,
+            return_component_opt: None,
+            exit_target_opt: None,
+            avoided_keys: vec![],
+        };
+        let unsuccessful_three_hop_route = addr.send(three_hop_route_request);
+        let public_key_query = addr.send(NodeQueryMessage::PublicKey(a.public_key().clone()));
+        let failed_ip_address_query = addr.send(NodeQueryMessage::IpAddress(
+            a.node_addr_opt().unwrap().ip_addr(),
+        ));
+        System::current().stop_with_code(0);
 
-        assert_eq!(routes, Vec::<Vec<&PublicKey>>::new());
+        system.run();
+        assert_eq!(None, unsuccessful_three_hop_route.wait().unwrap());
+        assert_eq!(
+            a.public_key(),
+            &public_key_query.wait().unwrap().unwrap().public_key
+        );
+        assert_eq!(None, failed_ip_address_query.wait().unwrap());
     }
 
-    /*
-            Database:
-
-            P---q---R
+    fn hole_punch_payload(package: &IncipientCoresPackage, key: &PublicKey) -> HolePunch_0v1 {
+        match decodex::<MessageType>(&CryptDENull::from(key, DEFAULT_CHAIN_ID), &package.payload)
+            .unwrap()
+        {
+            MessageType::HolePunch(vd) => HolePunch_0v1::try_from(vd).unwrap(),
+            other => panic!("Expected HolePunch, got {:?}", other),
+        }
+    }
 
-            Test is written from the standpoint of P. Node q is non-routing.
-    */
+    fn benchmark_payload(package: &IncipientCoresPackage, key: &PublicKey) -> Benchmark_0v1 {
+        match decodex::<MessageType>(&CryptDENull::from(key, DEFAULT_CHAIN_ID), &package.payload)
+            .unwrap()
+        {
+            MessageType::Benchmark(vd) => Benchmark_0v1::try_from(vd).unwrap(),
+            other => panic!("Expected Benchmark, got {:?}", other),
+        }
+    }
 
-    #[test]
-    fn cant_route_through_non_routing_node() {
+    fn make_benchmarking_subject() -> (Neighborhood, NodeRecord) {
         let mut subject = make_standard_subject();
-        let db = &mut subject.neighborhood_database;
-        let p = &db.root_mut().public_key().clone(); // 9e7p7un06eHs6frl5A
-        let q = &db
-            .add_node(make_node_record_f(4567, true, false, false))
-            .unwrap(); // BAUGBw
-        let r = &db.add_node(make_node_record(5678, true)).unwrap(); // BQYHCA
-        db.add_arbitrary_full_neighbor(p, q);
-        db.add_arbitrary_full_neighbor(q, r);
-
-        // At least two hops from P to anywhere standard
-        let routes = subject.complete_routes(vec![p], None, 2, RouteDirection::Over);
-
-        let expected: Vec<Vec<&PublicKey>> = vec![];
-        assert_eq!(routes, expected);
+        subject.bandwidth_benchmark = true;
+        let n = subject.neighborhood_database.root().clone();
+        let mut neighbor = make_node_record(3456, true);
+        neighbor.set_features(
+            vec![LINK_BENCHMARK_FEATURE.to_string()]
+                .into_iter()
+                .collect(),
+        );
+        let bystander = make_node_record(4567, true);
+        {
+            let db = &mut subject.neighborhood_database;
+            db.add_node(neighbor.clone()).unwrap();
+            db.add_node(bystander.clone()).unwrap();
+            db.add_arbitrary_full_neighbor(n.public_key(), neighbor.public_key());
+            db.add_arbitrary_full_neighbor(n.public_key(), bystander.public_key());
+        }
+        (subject, neighbor)
     }
 
     #[test]
-    fn gossips_after_removing_a_neighbor() {
-        let (hopper, hopper_awaiter, hopper_recording) = make_recorder();
+    fn neighborhood_advertises_link_benchmark_feature_only_when_benchmarking() {
         let cryptde = main_cryptde();
-        let earning_wallet = make_wallet("earning");
-        let consuming_wallet = Some(make_paying_wallet(b"consuming"));
-        let this_node = NodeRecord::new_for_tests(
-            &cryptde.public_key(),
-            Some(&NodeAddr::new(
-                &IpAddr::from_str("5.4.3.2").unwrap(),
-                &[1234],
-            )),
-            100,
-            true,
-            true,
+        let mut config = bc_from_nc_plus(
+            NeighborhoodConfig {
+                mode: NeighborhoodMode::ZeroHop,
+            },
+            make_wallet("earning"),
+            None,
+            "neighborhood_advertises_link_benchmark_feature_only_when_benchmarking",
         );
-        let this_node_inside = this_node.clone();
-        let removed_neighbor = make_node_record(2345, true);
-        let removed_neighbor_inside = removed_neighbor.clone();
-        let other_neighbor = make_node_record(3456, true);
-        let other_neighbor_inside = other_neighbor.clone();
-
-        thread::spawn(move || {
-            let system = System::new("gossips_after_removing_a_neighbor");
-            let mut subject = Neighborhood::new(
-                cryptde,
-                &bc_from_nc_plus(
-                    NeighborhoodConfig {
-                        mode: NeighborhoodMode::Standard(
-                            this_node_inside.node_addr_opt().unwrap(),
-                            vec![],
-                            rate_pack(100),
-                        ),
-                    },
-                    earning_wallet.clone(),
-                    consuming_wallet.clone(),
-                    "gossips_after_removing_a_neighbor",
-                ),
-            );
-            let db = &mut subject.neighborhood_database;
+        let without = Neighborhood::new(cryptde, &config);
+        config.bandwidth_benchmark = true;
 
-            db.add_node(removed_neighbor_inside.clone()).unwrap();
-            db.add_node(other_neighbor_inside.clone()).unwrap();
-            db.add_arbitrary_full_neighbor(
-                &cryptde.public_key(),
-                removed_neighbor_inside.public_key(),
-            );
-            db.add_arbitrary_full_neighbor(
-                &cryptde.public_key(),
-                other_neighbor_inside.public_key(),
-            );
-            db.add_arbitrary_full_neighbor(
-                removed_neighbor_inside.public_key(),
-                other_neighbor_inside.public_key(),
-            );
+        let with = Neighborhood::new(cryptde, &config);
 
-            let addr: Addr<Neighborhood> = subject.start();
-            let peer_actors = peer_actors_builder().hopper(hopper).build();
-            addr.try_send(BindMessage { peer_actors }).unwrap();
+        assert_eq!(
+            without
+                .neighborhood_database
+                .root()
+                .features()
+                .contains(LINK_BENCHMARK_FEATURE),
+            false
+        );
+        let root = with.neighborhood_database.root();
+        assert_eq!(root.features().contains(LINK_BENCHMARK_FEATURE), true);
+        let advertised =
+            NodeRecordInner_0v1::try_from(GossipNodeRecord::from(root.clone())).unwrap();
+        assert_eq!(advertised.features.contains(LINK_BENCHMARK_FEATURE), true);
+    }
 
-            let sub: Recipient<RemoveNeighborMessage> = addr.recipient::<RemoveNeighborMessage>();
-            sub.try_send(RemoveNeighborMessage {
-                public_key: removed_neighbor_inside.public_key().clone(),
-            })
-            .unwrap();
+    #[test]
+    fn benchmark_round_measures_benchmarking_neighbors_and_publishes_the_results() {
+        let (mut subject, neighbor) = make_benchmarking_subject();
+        let neighbor_addr = SocketAddr::from_str("3.4.5.6:3456").unwrap();
+        let (hopper, _, hopper_recording_arc) = make_recorder();
+        let system = System::new(
+            "benchmark_round_measures_benchmarking_neighbors_and_publishes_the_results",
+        );
+        let peer_actors = peer_actors_builder().hopper(hopper).build();
+        subject.hopper = Some(peer_actors.hopper.from_hopper_client);
+        let version_before = subject.neighborhood_database.root().version();
 
-            system.run();
-        });
+        subject.start_benchmark_round();
+        subject.handle_benchmark(neighbor_addr, Benchmark_0v1::Echo { nonce: 0 });
+        subject.handle_benchmark(neighbor_addr, Benchmark_0v1::Echo { nonce: 1 });
+        subject.start_benchmark_round();
 
-        let other_neighbor_cryptde =
-            CryptDENull::from(other_neighbor.public_key(), DEFAULT_CHAIN_ID);
-        hopper_awaiter.await_message_count(1);
-        let locked_recording = hopper_recording.lock().unwrap();
-        let package: &IncipientCoresPackage = locked_recording.get_record(0);
-        let gossip = match decodex(&other_neighbor_cryptde, &package.payload).unwrap() {
-            MessageType::Gossip(vd) => Gossip_0v1::try_from(vd).unwrap(),
-            x => panic!("Expected MessageType::Gossip, got {:?}", x),
-        };
-        type Digest = (PublicKey, Vec<u8>, bool, u32, Vec<PublicKey>);
-        let to_actual_digest = |gnr: GossipNodeRecord| {
-            let node_addr_opt = gnr.node_addr_opt.clone();
-            let inner = NodeRecordInner_0v1::try_from(gnr).unwrap();
-            let neighbors_vec = inner.neighbors.into_iter().collect::<Vec<PublicKey>>();
-            (
-                inner.public_key.clone(),
-                inner.public_key.into(),
-                node_addr_opt.is_some(),
-                inner.version,
-                neighbors_vec,
-            )
-        };
+        System::current().stop();
+        system.run();
+        let hopper_recording = hopper_recording_arc.lock().unwrap();
+        let package = hopper_recording.get_record::<IncipientCoresPackage>(0);
+        assert_eq!(
+            benchmark_payload(package, neighbor.public_key()),
+            Benchmark_0v1::Probe {
+                nonce: 0,
+                padding: PlainData::new(&[]),
+            }
+        );
+        assert_eq!(
+            package.route,
+            subject.create_single_hop_route(neighbor.public_key())
+        );
+        let package = hopper_recording.get_record::<IncipientCoresPackage>(1);
+        assert_eq!(
+            benchmark_payload(package, neighbor.public_key()),
+            Benchmark_0v1::Probe {
+                nonce: 1,
+                padding: PlainData::from(vec![0u8; BENCHMARK_PROBE_BYTES]),
+            }
+        );
+        let root = subject.neighborhood_database.root();
+        assert_eq!(root.version(), version_before + 1);
+        assert_eq!(
+            root.link_performance().keys().collect::<Vec<&PublicKey>>(),
+            vec![neighbor.public_key()]
+        );
+        let advertised =
+            NodeRecordInner_0v1::try_from(GossipNodeRecord::from(root.clone())).unwrap();
+        assert_eq!(&advertised.link_performance, root.link_performance());
+    }
 
-        let sort_digests = |digests: Vec<Digest>| {
-            let mut digests = digests
-                .into_iter()
-                .map(|mut d| {
-                    d.4.sort_unstable_by(|a, b| a.cmp(&b));
-                    d
-                })
-                .collect_vec();
-            digests.sort_unstable_by(|a, b| a.0.cmp(&b.0));
-            digests
-        };
+    #[test]
+    fn echoes_that_answer_no_probe_or_someone_elses_are_ignored() {
+        let (mut subject, neighbor) = make_benchmarking_subject();
+        let (hopper, _, hopper_recording_arc) = make_recorder();
+        let system = System::new("echoes_that_answer_no_probe_or_someone_elses_are_ignored");
+        let peer_actors = peer_actors_builder().hopper(hopper).build();
+        subject.hopper = Some(peer_actors.hopper.from_hopper_client);
+        subject.start_benchmark_round();
 
-        let actual_digests = sort_digests(
-            gossip
-                .node_records
-                .into_iter()
-                .map(|gnr| to_actual_digest(gnr))
-                .collect::<Vec<Digest>>(),
+        subject.handle_benchmark(
+            SocketAddr::from_str("4.5.6.7:4567").unwrap(),
+            Benchmark_0v1::Echo { nonce: 0 },
+        );
+        subject.handle_benchmark(
+            SocketAddr::from_str("3.4.5.6:3456").unwrap(),
+            Benchmark_0v1::Echo { nonce: 1234 },
         );
 
-        let expected_digests = sort_digests(vec![
-            (
-                removed_neighbor.public_key().clone(),
-                removed_neighbor.public_key().clone().into(),
-                false,
-                0,
-                vec![
-                    other_neighbor.public_key().clone(),
-                    this_node.public_key().clone(),
-                ],
-            ),
-            (
-                this_node.public_key().clone(),
-                this_node.public_key().clone().into(),
-                true,
-                1,
-                vec![other_neighbor.public_key().clone()],
-            ),
-        ]);
-
-        assert_eq!(expected_digests, actual_digests);
+        System::current().stop();
+        system.run();
+        assert_eq!(hopper_recording_arc.lock().unwrap().len(), 1);
+        let (key, padded, _) = subject.benchmark_probes.get(&0).unwrap();
+        assert_eq!(key, neighbor.public_key());
+        assert_eq!(*padded, false);
+        assert_eq!(subject.link_performance.is_empty(), true);
     }
 
     #[test]
-    fn neighborhood_calls_gossip_acceptor_when_gossip_is_received() {
-        let handle_params_arc = Arc::new(Mutex::new(vec![]));
-        let gossip_acceptor = GossipAcceptorMock::new()
-            .handle_params(&handle_params_arc)
-            .handle_result(GossipAcceptanceResult::Ignored);
-        let subject_node = make_global_cryptde_node_record(1234, true); // 9e7p7un06eHs6frl5A
-        let neighbor = make_node_record(1111, true);
-        let mut subject = neighborhood_from_nodes(&subject_node, Some(&neighbor));
-        subject.gossip_acceptor = Box::new(gossip_acceptor);
-        let gossip = GossipBuilder::new(&subject.neighborhood_database)
-            .node(subject_node.public_key(), true)
-            .build();
-        let cores_package = ExpiredCoresPackage {
-            immediate_neighbor: subject_node.node_addr_opt().unwrap().into(),
-            paying_wallet: None,
-            remaining_route: make_meaningless_route(),
-            payload: gossip.clone(),
-            payload_len: 0,
+    fn benchmark_probe_from_a_neighbor_is_echoed_only_when_benchmarking() {
+        let (mut subject, neighbor) = make_benchmarking_subject();
+        let (hopper, _, hopper_recording_arc) = make_recorder();
+        let system =
+            System::new("benchmark_probe_from_a_neighbor_is_echoed_only_when_benchmarking");
+        let peer_actors = peer_actors_builder().hopper(hopper).build();
+        subject.hopper = Some(peer_actors.hopper.from_hopper_client);
+        let probe = Benchmark_0v1::Probe {
+            nonce: 1234,
+            padding: PlainData::new(&[0u8; 16]),
         };
-        let system = System::new("");
-        let addr: Addr<Neighborhood> = subject.start();
-        let peer_actors = peer_actors_builder().build();
-        addr.try_send(BindMessage { peer_actors }).unwrap();
-        let sub = addr.recipient::<ExpiredCoresPackage<Gossip_0v1>>();
 
-        sub.try_send(cores_package).unwrap();
+        subject.handle_benchmark(SocketAddr::from_str("3.4.5.6:3456").unwrap(), probe.clone());
+        subject.handle_benchmark(SocketAddr::from_str("5.6.7.8:5678").unwrap(), probe.clone());
+        subject.bandwidth_benchmark = false;
+        subject.handle_benchmark(SocketAddr::from_str("3.4.5.6:3456").unwrap(), probe);
 
         System::current().stop();
         system.run();
-        let mut handle_params = handle_params_arc.lock().unwrap();
-        let (call_database, call_agrs, call_gossip_source) = handle_params.remove(0);
-        assert!(handle_params.is_empty());
-        assert_eq!(&subject_node, call_database.root());
-        assert_eq!(1, call_database.keys().len());
-        let agrs: Vec<AccessibleGossipRecord> = gossip.try_into().unwrap();
-        assert_eq!(agrs, call_agrs);
-        let actual_gossip_source: SocketAddr = subject_node.node_addr_opt().unwrap().into();
-        assert_eq!(actual_gossip_source, call_gossip_source);
+        let hopper_recording = hopper_recording_arc.lock().unwrap();
+        assert_eq!(hopper_recording.len(), 1);
+        let package = hopper_recording.get_record::<IncipientCoresPackage>(0);
+        assert_eq!(
+            benchmark_payload(package, neighbor.public_key()),
+            Benchmark_0v1::Echo { nonce: 1234 }
+        );
     }
 
     #[test]
-    fn neighborhood_sends_only_an_acceptance_debut_when_an_acceptance_debut_is_provided() {
-        let introduction_target_node = make_node_record(7345, true);
-        let subject_node = make_global_cryptde_node_record(5555, true); // 9e7p7un06eHs6frl5A
-        let neighbor = make_node_record(1050, true);
-        let mut subject = neighborhood_from_nodes(&subject_node, Some(&neighbor));
-        subject
-            .neighborhood_database
-            .add_node(introduction_target_node.clone())
+    fn speed_test_times_probes_to_an_exit_that_benchmarks_and_back() {
+        let (subject, neighbor) = make_benchmarking_subject();
+        let root_key = subject.cryptde.public_key().clone();
+        let system = System::new("speed_test_times_probes_to_an_exit_that_benchmarks_and_back");
+        let subject_addr: Addr<Neighborhood> = subject.start();
+        let (hopper, _, hopper_recording_arc) = make_recorder();
+        let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
+        let peer_actors = peer_actors_builder()
+            .hopper(hopper)
+            .ui_gateway(ui_gateway)
+            .build();
+        subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+        let echo = |nonce: u64, padding_len: usize| {
+            ExpiredCoresPackage::new(
+                SocketAddr::from_str("3.4.5.6:3456").unwrap(),
+                None,
+                make_meaningless_route(),
+                Benchmark_0v1::RouteEcho {
+                    nonce,
+                    padding: PlainData::from(vec![0u8; padding_len]),
+                },
+                0,
+            )
+        };
+
+        subject_addr
+            .try_send(NodeFromUiMessage {
+                client_id: 1234,
+                body: UiSpeedTestRequest {
+                    hop_counts_opt: Some(vec![1, 2]),
+                }
+                .tmb(4321),
+            })
+            .unwrap();
+        subject_addr.try_send(echo(0, 0)).unwrap();
+        subject_addr
+            .try_send(echo(1, BENCHMARK_PROBE_BYTES))
             .unwrap();
 
-        subject.neighborhood_database.add_arbitrary_half_neighbor(
-            subject_node.public_key(),
-            introduction_target_node.public_key(),
+        System::current().stop();
+        system.run();
+        let hopper_recording = hopper_recording_arc.lock().unwrap();
+        assert_eq!(hopper_recording.len(), 2);
+        assert_eq!(
+            benchmark_payload(
+                hopper_recording.get_record::<IncipientCoresPackage>(0),
+                neighbor.public_key()
+            ),
+            Benchmark_0v1::RouteProbe {
+                nonce: 0,
+                originator: root_key.clone(),
+                padding: PlainData::new(&[]),
+            }
         );
-        let debut = GossipBuilder::new(&subject.neighborhood_database)
-            .node(subject_node.public_key(), true)
-            .build();
-        let gossip_acceptor =
-            GossipAcceptorMock::new().handle_result(GossipAcceptanceResult::Reply(
-                debut.clone(),
-                introduction_target_node.public_key().clone(),
-                introduction_target_node.node_addr_opt().unwrap(),
-            ));
-        subject.gossip_acceptor = Box::new(gossip_acceptor);
+        assert_eq!(
+            benchmark_payload(
+                hopper_recording.get_record::<IncipientCoresPackage>(1),
+                neighbor.public_key()
+            ),
+            Benchmark_0v1::RouteProbe {
+                nonce: 1,
+                originator: root_key,
+                padding: PlainData::from(vec![0u8; BENCHMARK_PROBE_BYTES]),
+            }
+        );
+        let ui_gateway_recording = ui_gateway_recording_arc.lock().unwrap();
+        let message = ui_gateway_recording.get_record::<NodeToUiMessage>(0);
+        assert_eq!(message.target, ClientId(1234));
+        let (response, context_id) = UiSpeedTestResponse::fmb(message.body.clone()).unwrap();
+        assert_eq!(context_id, 4321);
+        assert_eq!(
+            response
+                .results
+                .iter()
+                .map(|result| (result.hop_count, result.exit_key.clone()))
+                .collect::<Vec<(usize, String)>>(),
+            vec![(1, truncated_key(neighbor.public_key()))]
+        );
+        assert_eq!(response.unavailable_hop_counts, vec![2]);
+    }
+
+    #[test]
+    fn speed_test_probe_is_echoed_back_along_its_route_only_when_benchmarking() {
+        let (mut subject, _) = make_benchmarking_subject();
         let (hopper, _, hopper_recording_arc) = make_recorder();
+        let system =
+            System::new("speed_test_probe_is_echoed_back_along_its_route_only_when_benchmarking");
         let peer_actors = peer_actors_builder().hopper(hopper).build();
-        let system = System::new("");
-        subject.hopper_no_lookup = Some(peer_actors.hopper.from_hopper_client_no_lookup);
+        subject.hopper = Some(peer_actors.hopper.from_hopper_client);
+        let originator = PublicKey::new(b"originator");
+        let remaining_route = make_meaningless_route();
+        let padding = PlainData::new(&[0u8; 16]);
 
-        subject.handle_gossip(
-            Gossip_0v1::new(vec![]),
-            SocketAddr::from_str("1.1.1.1:1111").unwrap(),
-        );
+        subject.handle_route_probe(remaining_route.clone(), 1234, &originator, padding.clone());
+        subject.bandwidth_benchmark = false;
+        subject.handle_route_probe(remaining_route.clone(), 2345, &originator, padding.clone());
 
         System::current().stop();
         system.run();
         let hopper_recording = hopper_recording_arc.lock().unwrap();
-        let package = hopper_recording.get_record::<NoLookupIncipientCoresPackage>(0);
-        assert_eq!(1, hopper_recording.len());
-        assert_eq!(introduction_target_node.public_key(), &package.public_key);
-        let gossip = match decodex::<MessageType>(
-            &CryptDENull::from(introduction_target_node.public_key(), DEFAULT_CHAIN_ID),
-            &package.payload,
-        ) {
-            Ok(MessageType::Gossip(vd)) => Gossip_0v1::try_from(vd).unwrap(),
-            x => panic!("Wanted Gossip, found {:?}", x),
-        };
-        assert_eq!(debut, gossip);
+        assert_eq!(hopper_recording.len(), 1);
+        let package = hopper_recording.get_record::<IncipientCoresPackage>(0);
+        assert_eq!(package.route, remaining_route);
+        assert_eq!(
+            benchmark_payload(package, &originator),
+            Benchmark_0v1::RouteEcho {
+                nonce: 1234,
+                padding,
+            }
+        );
     }
 
     #[test]
-    fn neighborhood_transmits_gossip_failure_properly() {
-        let subject_node = make_global_cryptde_node_record(5555, true); // 9e7p7un06eHs6frl5A
-        let neighbor = make_node_record(1111, true);
-        let public_key = PublicKey::new(&[1, 2, 3, 4]);
-        let node_addr = NodeAddr::from_str("1.2.3.4:1234").unwrap();
-        let gossip_acceptor =
-            GossipAcceptorMock::new().handle_result(GossipAcceptanceResult::Failed(
-                GossipFailure_0v1::NoSuitableNeighbors,
-                public_key.clone(),
-                node_addr.clone(),
-            ));
-        let mut subject: Neighborhood = neighborhood_from_nodes(&subject_node, Some(&neighbor));
+    fn speed_test_request_for_too_many_hops_is_refused() {
+        let (subject, _) = make_benchmarking_subject();
+        let system = System::new("speed_test_request_for_too_many_hops_is_refused");
+        let subject_addr: Addr<Neighborhood> = subject.start();
         let (hopper, _, hopper_recording_arc) = make_recorder();
-        let system = System::new("neighborhood_transmits_gossip_failure_properly");
-        let peer_actors = peer_actors_builder().hopper(hopper).build();
-        subject.hopper_no_lookup = Some(peer_actors.hopper.from_hopper_client_no_lookup);
-        subject.gossip_acceptor = Box::new(gossip_acceptor);
+        let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
+        let peer_actors = peer_actors_builder()
+            .hopper(hopper)
+            .ui_gateway(ui_gateway)
+            .build();
+        subject_addr.try_send(BindMessage { peer_actors }).unwrap();
 
-        subject.handle_gossip_agrs(vec![], SocketAddr::from_str("1.2.3.4:1234").unwrap());
+        subject_addr
+            .try_send(NodeFromUiMessage {
+                client_id: 1234,
+                body: UiSpeedTestRequest {
+                    hop_counts_opt: Some(vec![1, MAX_QUOTED_HOP_COUNT + 1]),
+                }
+                .tmb(4321),
+            })
+            .unwrap();
 
         System::current().stop();
         system.run();
-        let hopper_recording = hopper_recording_arc.lock().unwrap();
-        let package = hopper_recording.get_record::<NoLookupIncipientCoresPackage>(0);
-        assert_eq!(1, hopper_recording.len());
-        assert_eq!(package.node_addr, node_addr);
-        let payload = decodex::<MessageType>(
-            &CryptDENull::from(&public_key, DEFAULT_CHAIN_ID),
-            &package.payload,
-        )
-        .unwrap();
+        assert_eq!(hopper_recording_arc.lock().unwrap().len(), 0);
+        let ui_gateway_recording = ui_gateway_recording_arc.lock().unwrap();
+        let message = ui_gateway_recording.get_record::<NodeToUiMessage>(0);
         assert_eq!(
-            payload,
-            MessageType::GossipFailure(VersionedData::new(
-                &crate::sub_lib::migrations::gossip_failure::MIGRATIONS,
-                &GossipFailure_0v1::NoSuitableNeighbors
+            message.body.payload,
+            Err((
+                SPEED_TEST_ERROR,
+                format!(
+                    "Can't test a {}-hop route: no more than {} hops, please",
+                    MAX_QUOTED_HOP_COUNT + 1,
+                    MAX_QUOTED_HOP_COUNT
+                )
             ))
         );
     }
 
-    struct DatabaseReplacementGossipAcceptor {
-        pub replacement_database: NeighborhoodDatabase,
+    #[test]
+    fn unreachable_neighbor_prompts_a_request_for_an_introduction_from_a_mutual_neighbor() {
+        let mut subject = make_standard_subject();
+        let n = &subject.neighborhood_database.root().clone();
+        let unreachable = &make_node_record(3456, true);
+        let rendezvous = &make_node_record(4567, true);
+        {
+            let db = &mut subject.neighborhood_database;
+            db.add_node(unreachable.clone()).unwrap();
+            db.add_node(rendezvous.clone()).unwrap();
+            db.add_arbitrary_half_neighbor(n.public_key(), unreachable.public_key());
+            db.add_arbitrary_full_neighbor(n.public_key(), rendezvous.public_key());
+            db.add_arbitrary_half_neighbor(rendezvous.public_key(), unreachable.public_key());
+        }
+        let (hopper, _, hopper_recording_arc) = make_recorder();
+        let system = System::new("unreachable_neighbor_prompts_a_request_for_an_introduction");
+        let peer_actors = peer_actors_builder().hopper(hopper).build();
+        subject.hopper = Some(peer_actors.hopper.from_hopper_client);
+
+        subject.request_hole_punch(unreachable.public_key());
+        subject.request_hole_punch(unreachable.public_key());
+
+        System::current().stop();
+        system.run();
+        let hopper_recording = hopper_recording_arc.lock().unwrap();
+        assert_eq!(hopper_recording.len(), 1);
+        let package = hopper_recording.get_record::<IncipientCoresPackage>(0);
+        let (port, _) = *subject.hole_punches.get(unreachable.public_key()).unwrap();
+        assert_eq!(
+            hole_punch_payload(package, rendezvous.public_key()),
+            HolePunch_0v1::Request {
+                target: unreachable.public_key().clone(),
+                port,
+            }
+        );
+        assert_eq!(
+            package.route,
+            subject.create_single_hop_route(rendezvous.public_key())
+        );
     }
 
-    impl GossipAcceptor for DatabaseReplacementGossipAcceptor {
-        fn handle(
-            &self,
-            database: &mut NeighborhoodDatabase,
-            _agrs: Vec<AccessibleGossipRecord>,
-            _gossip_source: SocketAddr,
-        ) -> GossipAcceptanceResult {
-            let non_root_database_keys = database
-                .keys()
-                .into_iter()
-                .filter(|k| *k != database.root().public_key())
-                .map(|k| k.clone())
-                .collect_vec();
-            non_root_database_keys
-                .into_iter()
-                .for_each(|k| database.remove_node(&k));
-            let database_root_neighbor_keys = database
-                .root()
-                .half_neighbor_keys()
-                .into_iter()
-                .map(|k| k.clone())
-                .collect_vec();
-            database_root_neighbor_keys.into_iter().for_each(|k| {
-                database.root_mut().remove_half_neighbor_key(&k);
-            });
-            self.replacement_database
-                .keys()
-                .into_iter()
-                .filter(|k| *k != self.replacement_database.root().public_key())
-                .for_each(|k| {
-                    database
-                        .add_node(self.replacement_database.node_by_key(k).unwrap().clone())
-                        .unwrap();
-                });
-            self.replacement_database.keys().into_iter().for_each(|k| {
-                let node_record = self.replacement_database.node_by_key(k).unwrap();
-                node_record.half_neighbor_keys().into_iter().for_each(|n| {
-                    database.add_arbitrary_half_neighbor(k, n);
-                });
-            });
-            GossipAcceptanceResult::Ignored
+    #[test]
+    fn unreachable_neighbor_with_no_mutual_neighbor_prompts_nothing() {
+        let mut subject = make_standard_subject();
+        let n = &subject.neighborhood_database.root().clone();
+        let unreachable = &make_node_record(3456, true);
+        let other = &make_node_record(4567, true);
+        {
+            let db = &mut subject.neighborhood_database;
+            db.add_node(unreachable.clone()).unwrap();
+            db.add_node(other.clone()).unwrap();
+            db.add_arbitrary_half_neighbor(n.public_key(), unreachable.public_key());
+            db.add_arbitrary_full_neighbor(n.public_key(), other.public_key());
         }
+        let (hopper, _, hopper_recording_arc) = make_recorder();
+        let system = System::new("unreachable_neighbor_with_no_mutual_neighbor_prompts_nothing");
+        let peer_actors = peer_actors_builder().hopper(hopper).build();
+        subject.hopper = Some(peer_actors.hopper.from_hopper_client);
+
+        subject.request_hole_punch(unreachable.public_key());
+
+        System::current().stop();
+        system.run();
+        assert_eq!(hopper_recording_arc.lock().unwrap().len(), 0);
+        assert_eq!(subject.hole_punches.is_empty(), true);
     }
 
-    fn bind_subject(subject: &mut Neighborhood, peer_actors: PeerActors) {
+    #[test]
+    fn hole_punch_request_from_a_neighbor_introduces_it_to_the_target() {
+        let mut subject = make_standard_subject();
+        let n = &subject.neighborhood_database.root().clone();
+        let requester = &make_node_record(3456, true);
+        let target = &make_node_record(4567, true);
+        {
+            let db = &mut subject.neighborhood_database;
+            db.add_node(requester.clone()).unwrap();
+            db.add_node(target.clone()).unwrap();
+            db.add_arbitrary_full_neighbor(n.public_key(), requester.public_key());
+            db.add_arbitrary_full_neighbor(n.public_key(), target.public_key());
+        }
+        let (hopper, _, hopper_recording_arc) = make_recorder();
+        let system = System::new("hole_punch_request_from_a_neighbor_introduces_it_to_the_target");
+        let peer_actors = peer_actors_builder().hopper(hopper).build();
         subject.hopper = Some(peer_actors.hopper.from_hopper_client);
-        subject.hopper_no_lookup = Some(peer_actors.hopper.from_hopper_client_no_lookup);
-        subject.connected_signal = Some(peer_actors.accountant.start);
+
+        subject.handle_hole_punch(
+            SocketAddr::from_str("3.4.5.6:3456").unwrap(),
+            HolePunch_0v1::Request {
+                target: target.public_key().clone(),
+                port: 4321,
+            },
+        );
+
+        System::current().stop();
+        system.run();
+        let hopper_recording = hopper_recording_arc.lock().unwrap();
+        let package = hopper_recording.get_record::<IncipientCoresPackage>(0);
+        assert_eq!(
+            hole_punch_payload(package, target.public_key()),
+            HolePunch_0v1::Introduction {
+                peer: requester.public_key().clone(),
+                peer_addr: SocketAddr::from_str("3.4.5.6:4321").unwrap(),
+            }
+        );
+        assert_eq!(
+            package.route,
+            subject.create_single_hop_route(target.public_key())
+        );
     }
 
     #[test]
-    fn neighborhood_does_not_start_accountant_if_no_route_can_be_made() {
-        let subject_node = make_global_cryptde_node_record(5555, true); // 9e7p7un06eHs6frl5A
-        let neighbor = make_node_record(1111, true);
-        let mut subject: Neighborhood = neighborhood_from_nodes(&subject_node, Some(&neighbor));
-        let mut replacement_database = subject.neighborhood_database.clone();
-        replacement_database.add_node(neighbor.clone()).unwrap();
-        replacement_database
-            .add_arbitrary_half_neighbor(subject_node.public_key(), neighbor.public_key());
-        subject.gossip_acceptor = Box::new(DatabaseReplacementGossipAcceptor {
-            replacement_database,
-        });
-        let (accountant, _, accountant_recording_arc) = make_recorder();
-        let system = System::new("neighborhood_does_not_start_accountant_if_no_route_can_be_made");
-        let peer_actors = peer_actors_builder().accountant(accountant).build();
-        bind_subject(&mut subject, peer_actors);
+    fn hole_punch_request_is_ignored_unless_both_nodes_are_neighbors() {
+        init_test_logging();
+        let mut subject = make_standard_subject();
+        let n = &subject.neighborhood_database.root().clone();
+        let neighbor = &make_node_record(3456, true);
+        let stranger = &make_node_record(4567, true);
+        {
+            let db = &mut subject.neighborhood_database;
+            db.add_node(neighbor.clone()).unwrap();
+            db.add_node(stranger.clone()).unwrap();
+            db.add_arbitrary_full_neighbor(n.public_key(), neighbor.public_key());
+        }
+        let (hopper, _, hopper_recording_arc) = make_recorder();
+        let system = System::new("hole_punch_request_is_ignored_unless_both_nodes_are_neighbors");
+        let peer_actors = peer_actors_builder().hopper(hopper).build();
+        subject.hopper = Some(peer_actors.hopper.from_hopper_client);
 
-        subject.handle_gossip_agrs(vec![], SocketAddr::from_str("1.2.3.4:1234").unwrap());
+        subject.handle_hole_punch(
+            SocketAddr::from_str("4.5.6.7:4567").unwrap(),
+            HolePunch_0v1::Request {
+                target: neighbor.public_key().clone(),
+                port: 4321,
+            },
+        );
+        subject.handle_hole_punch(
+            SocketAddr::from_str("3.4.5.6:3456").unwrap(),
+            HolePunch_0v1::Request {
+                target: stranger.public_key().clone(),
+                port: 4321,
+            },
+        );
 
         System::current().stop();
         system.run();
-        let accountant_recording = accountant_recording_arc.lock().unwrap();
-        assert_eq!(accountant_recording.len(), 0);
-        assert_eq!(subject.is_connected, false);
+        assert_eq!(hopper_recording_arc.lock().unwrap().len(), 0);
+        let tlh = TestLogHandler::new();
+        tlh.exists_log_containing(
+            "WARN: Neighborhood: Ignoring hole-punching Request from 4.5.6.7, which is not a neighbor",
+        );
+        tlh.exists_log_containing(&format!(
+            "WARN: Neighborhood: Ignoring hole-punching Request from {} for {}, which is not a neighbor",
+            neighbor.public_key(),
+            stranger.public_key()
+        ));
     }
 
     #[test]
-    fn neighborhood_does_not_start_accountant_if_already_connected() {
-        let subject_node = make_global_cryptde_node_record(5555, true); // 9e7p7un06eHs6frl5A
-        let neighbor = make_node_record(1111, true);
-        let mut subject: Neighborhood = neighborhood_from_nodes(&subject_node, Some(&neighbor));
-        let replacement_database = subject.neighborhood_database.clone();
-        subject.gossip_acceptor = Box::new(DatabaseReplacementGossipAcceptor {
-            replacement_database,
-        });
-        subject.is_connected = true;
-        let (accountant, _, accountant_recording_arc) = make_recorder();
-        let system = System::new("neighborhood_does_not_start_accountant_if_no_route_can_be_made");
-        let peer_actors = peer_actors_builder().accountant(accountant).build();
-        bind_subject(&mut subject, peer_actors);
+    fn introduction_we_asked_for_punches_a_hole_from_the_port_we_promised() {
+        let mut subject = make_standard_subject();
+        let peer = &make_node_record(3456, true);
+        subject
+            .hole_punches
+            .insert(peer.public_key().clone(), (1234, Instant::now()));
+        let (hopper, _, hopper_recording_arc) = make_recorder();
+        let (dispatcher, _, dispatcher_recording_arc) = make_recorder();
+        let system = System::new("introduction_we_asked_for_punches_a_hole");
+        let peer_actors = peer_actors_builder()
+            .hopper(hopper)
+            .dispatcher(dispatcher)
+            .build();
+        subject.hopper = Some(peer_actors.hopper.from_hopper_client);
+        subject.punch_hole_sub = Some(peer_actors.dispatcher.punch_hole_sub);
 
-        subject.handle_gossip_agrs(vec![], SocketAddr::from_str("1.2.3.4:1234").unwrap());
+        subject.handle_hole_punch(
+            SocketAddr::from_str("4.5.6.7:4567").unwrap(),
+            HolePunch_0v1::Introduction {
+                peer: peer.public_key().clone(),
+                peer_addr: SocketAddr::from_str("3.4.5.6:5678").unwrap(),
+            },
+        );
 
         System::current().stop();
         system.run();
-        let accountant_recording = accountant_recording_arc.lock().unwrap();
-        assert_eq!(accountant_recording.len(), 0);
-        assert_eq!(subject.is_connected, true);
+        assert_eq!(hopper_recording_arc.lock().unwrap().len(), 0);
+        let dispatcher_recording = dispatcher_recording_arc.lock().unwrap();
+        assert_eq!(
+            dispatcher_recording.get_record::<PunchHoleMsg>(0),
+            &PunchHoleMsg {
+                peer_addr: SocketAddr::from_str("3.4.5.6:5678").unwrap(),
+                local_port: 1234,
+            }
+        );
+        assert_eq!(subject.hole_punches.is_empty(), true);
     }
 
     #[test]
-    fn neighborhood_starts_accountant_when_first_route_can_be_made() {
-        let subject_node = make_global_cryptde_node_record(5555, true); // 9e7p7un06eHs6frl5A
-        let relay1 = make_node_record(1111, true);
-        let relay2 = make_node_record(2222, false);
-        let exit = make_node_record(3333, false);
-        let mut subject: Neighborhood = neighborhood_from_nodes(&subject_node, Some(&relay1));
-        let mut replacement_database = subject.neighborhood_database.clone();
-        replacement_database.add_node(relay1.clone()).unwrap();
-        replacement_database.add_node(relay2.clone()).unwrap();
-        replacement_database.add_node(exit.clone()).unwrap();
-        replacement_database
-            .add_arbitrary_full_neighbor(subject_node.public_key(), relay1.public_key());
-        replacement_database.add_arbitrary_full_neighbor(relay1.public_key(), relay2.public_key());
-        replacement_database.add_arbitrary_full_neighbor(relay2.public_key(), exit.public_key());
-        subject.gossip_acceptor = Box::new(DatabaseReplacementGossipAcceptor {
-            replacement_database,
-        });
-        subject.persistent_config_opt = Some(Box::new(
-            PersistentConfigurationMock::new().set_past_neighbors_result(Ok(())),
-        ));
-        subject.is_connected = false;
-        let (accountant, _, accountant_recording_arc) = make_recorder();
-        let system = System::new("neighborhood_does_not_start_accountant_if_no_route_can_be_made");
-        let peer_actors = peer_actors_builder().accountant(accountant).build();
-        bind_subject(&mut subject, peer_actors);
+    fn unexpected_introduction_is_answered_through_the_rendezvous_and_punched() {
+        let mut subject = make_standard_subject();
+        let n = &subject.neighborhood_database.root().clone();
+        let peer = &make_node_record(3456, true);
+        let rendezvous = &make_node_record(4567, true);
+        {
+            let db = &mut subject.neighborhood_database;
+            db.add_node(rendezvous.clone()).unwrap();
+            db.add_arbitrary_full_neighbor(n.public_key(), rendezvous.public_key());
+        }
+        let (hopper, _, hopper_recording_arc) = make_recorder();
+        let (dispatcher, _, dispatcher_recording_arc) = make_recorder();
+        let system = System::new("unexpected_introduction_is_answered_and_punched");
+        let peer_actors = peer_actors_builder()
+            .hopper(hopper)
+            .dispatcher(dispatcher)
+            .build();
+        subject.hopper = Some(peer_actors.hopper.from_hopper_client);
+        subject.punch_hole_sub = Some(peer_actors.dispatcher.punch_hole_sub);
 
-        subject.handle_gossip_agrs(vec![], SocketAddr::from_str("1.2.3.4:1234").unwrap());
+        subject.handle_hole_punch(
+            SocketAddr::from_str("4.5.6.7:4567").unwrap(),
+            HolePunch_0v1::Introduction {
+                peer: peer.public_key().clone(),
+                peer_addr: SocketAddr::from_str("3.4.5.6:5678").unwrap(),
+            },
+        );
 
         System::current().stop();
         system.run();
-        let accountant_recording = accountant_recording_arc.lock().unwrap();
-        assert_eq!(accountant_recording.len(), 1);
-        assert_eq!(subject.is_connected, true);
+        let dispatcher_recording = dispatcher_recording_arc.lock().unwrap();
+        let punch_hole_msg = dispatcher_recording.get_record::<PunchHoleMsg>(0);
+        assert_eq!(
+            punch_hole_msg.peer_addr,
+            SocketAddr::from_str("3.4.5.6:5678").unwrap()
+        );
+        let hopper_recording = hopper_recording_arc.lock().unwrap();
+        let package = hopper_recording.get_record::<IncipientCoresPackage>(0);
+        assert_eq!(
+            hole_punch_payload(package, rendezvous.public_key()),
+            HolePunch_0v1::Request {
+                target: peer.public_key().clone(),
+                port: punch_hole_msg.local_port,
+            }
+        );
+        assert_eq!(
+            subject
+                .hole_punches
+                .get(peer.public_key())
+                .map(|(port, _)| *port),
+            Some(punch_hole_msg.local_port)
+        );
     }
 
-    struct NeighborReplacementGossipAcceptor {
-        pub new_neighbors: Vec<NodeRecord>,
+    fn node_record_to_neighbor_config(node_record_ref: &NodeRecord) -> NodeDescriptor {
+        let cryptde: &dyn CryptDE = main_cryptde();
+        NodeDescriptor::from((
+            &node_record_ref.public_key().clone(),
+            &node_record_ref.node_addr_opt().unwrap().clone(),
+            DEFAULT_CHAIN_ID == chain_id_from_name(DEFAULT_CHAIN_NAME),
+            cryptde,
+        ))
     }
 
-    impl GossipAcceptor for NeighborReplacementGossipAcceptor {
-        fn handle(
-            &self,
-            database: &mut NeighborhoodDatabase,
-            _agrs: Vec<AccessibleGossipRecord>,
-            _gossip_source: SocketAddr,
-        ) -> GossipAcceptanceResult {
-            let half_neighbor_keys = database
-                .root()
-                .half_neighbor_keys()
-                .into_iter()
-                .map(|k| k.clone())
-                .collect_vec();
-            half_neighbor_keys
-                .into_iter()
-                .for_each(|k| database.remove_node(&k));
-            let root_key = database.root().public_key().clone();
-            self.new_neighbors.iter().for_each(|nr| {
-                database.add_node(nr.clone()).unwrap();
-                database.add_arbitrary_full_neighbor(&root_key, nr.public_key());
-            });
-            GossipAcceptanceResult::Ignored
-        }
+    #[test]
+    fn neighborhood_sends_node_query_response_with_none_when_initially_configured_with_no_data() {
+        let cryptde = main_cryptde();
+        let (recorder, awaiter, recording_arc) = make_recorder();
+        thread::spawn(move || {
+            let system = System::new("responds_with_none_when_initially_configured_with_no_data");
+
+            let addr: Addr<Recorder> = recorder.start();
+            let recipient: Recipient<DispatcherNodeQueryResponse> =
+                addr.recipient::<DispatcherNodeQueryResponse>();
+
+            let subject = make_standard_subject();
+            let addr: Addr<Neighborhood> = subject.start();
+            let sub: Recipient<DispatcherNodeQueryMessage> =
+                addr.recipient::<DispatcherNodeQueryMessage>();
+
+            sub.try_send(DispatcherNodeQueryMessage {
+                query: NodeQueryMessage::PublicKey(PublicKey::new(&b"booga"[..])),
+                context: TransmitDataMsg {
+                    endpoint: Endpoint::Key(cryptde.public_key().clone()),
+                    last_data: false,
+                    sequence_number: None,
+                    data: Vec::new(),
+                    relayed_from_opt: None,
+                },
+                recipient,
+            })
+            .unwrap();
+
+            system.run();
+        });
+
+        awaiter.await_message_count(1);
+        let recording = recording_arc.lock().unwrap();
+        assert_eq!(recording.len(), 1);
+        let message = recording.get_record::<DispatcherNodeQueryResponse>(0);
+        assert_eq!(message.result, None);
     }
 
     #[test]
-    fn neighborhood_updates_past_neighbors_when_neighbor_list_changes() {
+    fn neighborhood_sends_node_query_response_with_none_when_key_query_matches_no_configured_data()
+    {
         let cryptde: &dyn CryptDE = main_cryptde();
-        let subject_node = make_global_cryptde_node_record(5555, true); // 9e7p7un06eHs6frl5A
-        let old_neighbor = make_node_record(1111, true);
-        let new_neighbor = make_node_record(2222, true);
-        let mut subject: Neighborhood = neighborhood_from_nodes(&subject_node, Some(&old_neighbor));
-        subject
-            .neighborhood_database
-            .add_node(old_neighbor.clone())
-            .unwrap();
-        subject
-            .neighborhood_database
-            .add_arbitrary_full_neighbor(subject_node.public_key(), old_neighbor.public_key());
-        let gossip_acceptor = NeighborReplacementGossipAcceptor {
-            new_neighbors: vec![old_neighbor.clone(), new_neighbor.clone()],
-        };
-        let set_past_neighbors_params_arc = Arc::new(Mutex::new(vec![]));
-        let persistent_config = PersistentConfigurationMock::new()
-            .set_past_neighbors_params(&set_past_neighbors_params_arc)
-            .set_past_neighbors_result(Ok(()));
-        subject.gossip_acceptor = Box::new(gossip_acceptor);
-        subject.persistent_config_opt = Some(Box::new(persistent_config));
-
-        subject.handle_gossip_agrs(vec![], SocketAddr::from_str("1.2.3.4:1234").unwrap());
+        let earning_wallet = make_wallet("earning");
+        let consuming_wallet = Some(make_paying_wallet(b"consuming"));
+        let (recorder, awaiter, recording_arc) = make_recorder();
+        thread::spawn(move || {
+            let system = System::new("neighborhood_sends_node_query_response_with_none_when_key_query_matches_no_configured_data");
+            let addr: Addr<Recorder> = recorder.start();
+            let recipient: Recipient<DispatcherNodeQueryResponse> =
+                addr.recipient::<DispatcherNodeQueryResponse>();
 
-        let mut set_past_neighbors_params = set_past_neighbors_params_arc.lock().unwrap();
-        let (neighbors_opt, db_password) = set_past_neighbors_params.remove(0);
-        let neighbors = neighbors_opt.unwrap();
-        assert_contains(
-            &neighbors,
-            &NodeDescriptor::from((
-                &old_neighbor,
-                DEFAULT_CHAIN_ID == chain_id_from_name(DEFAULT_CHAIN_NAME),
-                cryptde,
-            )),
-        );
-        assert_contains(
-            &neighbors,
-            &NodeDescriptor::from((
-                &new_neighbor,
-                DEFAULT_CHAIN_ID == chain_id_from_name(DEFAULT_CHAIN_NAME),
+            let subject = Neighborhood::new(
                 cryptde,
-            )),
-        );
-        assert_eq!(neighbors.len(), 2);
-        assert_eq!(db_password, "password".to_string());
+                &bc_from_nc_plus(
+                    NeighborhoodConfig {
+                        mode: NeighborhoodMode::Standard(
+                            NodeAddr::new(&IpAddr::from_str("5.4.3.2").unwrap(), &[5678]),
+                            vec![NodeDescriptor::from((
+                                &PublicKey::new(&b"booga"[..]),
+                                &NodeAddr::new(
+                                    &IpAddr::from_str("1.2.3.4").unwrap(),
+                                    &[1234, 2345],
+                                ),
+                                DEFAULT_CHAIN_ID == chain_id_from_name(DEFAULT_CHAIN_NAME),
+                                cryptde,
+                            ))],
+                            rate_pack(100),
+                        ),
+                    },
+                    earning_wallet.clone(),
+                    consuming_wallet.clone(),
+                    "neighborhood_sends_node_query_response_with_none_when_key_query_matches_no_configured_data"
+                ),
+            );
+            let addr: Addr<Neighborhood> = subject.start();
+            let sub: Recipient<DispatcherNodeQueryMessage> =
+                addr.recipient::<DispatcherNodeQueryMessage>();
+
+            sub.try_send(DispatcherNodeQueryMessage {
+                query: NodeQueryMessage::PublicKey(PublicKey::new(&b"blah"[..])),
+                context: TransmitDataMsg {
+                    endpoint: Endpoint::Key(cryptde.public_key().clone()),
+                    last_data: false,
+                    sequence_number: None,
+                    data: Vec::new(),
+                    relayed_from_opt: None,
+                },
+                recipient,
+            })
+            .unwrap();
+
+            system.run();
+        });
+
+        awaiter.await_message_count(1);
+        let recording = recording_arc.lock().unwrap();
+        assert_eq!(recording.len(), 1);
+        let message = recording.get_record::<DispatcherNodeQueryResponse>(0);
+        assert_eq!(message.result, None);
     }
 
     #[test]
-    fn neighborhood_removes_past_neighbors_when_neighbor_list_goes_empty() {
-        let subject_node = make_global_cryptde_node_record(5555, true); // 9e7p7un06eHs6frl5A
-        let neighbor = make_node_record(1111, true);
-        let mut subject: Neighborhood = neighborhood_from_nodes(&subject_node, Some(&neighbor));
-        subject
-            .neighborhood_database
-            .add_node(neighbor.clone())
-            .unwrap();
-        subject
-            .neighborhood_database
-            .add_arbitrary_full_neighbor(subject_node.public_key(), neighbor.public_key());
-        let gossip_acceptor = NeighborReplacementGossipAcceptor {
-            new_neighbors: vec![],
+    fn neighborhood_sends_node_query_response_with_result_when_key_query_matches_configured_data() {
+        let cryptde = main_cryptde();
+        let earning_wallet = make_wallet("earning");
+        let consuming_wallet = Some(make_paying_wallet(b"consuming"));
+        let (recorder, awaiter, recording_arc) = make_recorder();
+        let one_neighbor = make_node_record(2345, true);
+        let another_neighbor = make_node_record(3456, true);
+        let another_neighbor_a = another_neighbor.clone();
+        let context = TransmitDataMsg {
+            endpoint: Endpoint::Key(cryptde.public_key().clone()),
+            last_data: false,
+            sequence_number: None,
+            data: Vec::new(),
+            relayed_from_opt: None,
         };
-        let set_past_neighbors_params_arc = Arc::new(Mutex::new(vec![]));
-        let persistent_config = PersistentConfigurationMock::new()
-            .set_past_neighbors_params(&set_past_neighbors_params_arc)
-            .set_past_neighbors_result(Ok(()));
-        subject.gossip_acceptor = Box::new(gossip_acceptor);
-        subject.persistent_config_opt = Some(Box::new(persistent_config));
+        let context_a = context.clone();
+        thread::spawn(move || {
+            let system = System::new("neighborhood_sends_node_query_response_with_result_when_key_query_matches_configured_data");
+            let addr: Addr<Recorder> = recorder.start();
+            let recipient = addr.recipient::<DispatcherNodeQueryResponse>();
+            let mut subject = Neighborhood::new(
+                cryptde,
+                &bc_from_nc_plus(
+                    NeighborhoodConfig {
+                        mode: NeighborhoodMode::Standard(
+                            NodeAddr::new(&IpAddr::from_str("5.4.3.2").unwrap(), &[5678]),
+                            vec![node_record_to_neighbor_config(&one_neighbor)],
+                            rate_pack(100),
+                        ),
+                    },
+                    earning_wallet.clone(),
+                    consuming_wallet.clone(),
+                    "neighborhood_sends_node_query_response_with_result_when_key_query_matches_configured_data"
+                ),
+            );
+            subject
+                .neighborhood_database
+                .add_node(another_neighbor.clone())
+                .unwrap();
+            let addr: Addr<Neighborhood> = subject.start();
+            let sub: Recipient<DispatcherNodeQueryMessage> =
+                addr.recipient::<DispatcherNodeQueryMessage>();
 
-        subject.handle_gossip_agrs(vec![], SocketAddr::from_str("1.2.3.4:1234").unwrap());
+            sub.try_send(DispatcherNodeQueryMessage {
+                query: NodeQueryMessage::PublicKey(another_neighbor.public_key().clone()),
+                context,
+                recipient,
+            })
+            .unwrap();
 
-        let mut set_past_neighbors_params = set_past_neighbors_params_arc.lock().unwrap();
-        let (neighbors_opt, db_password) = set_past_neighbors_params.remove(0);
-        assert_eq!(neighbors_opt, None);
-        assert_eq!(db_password, "password".to_string());
+            system.run();
+        });
+
+        awaiter.await_message_count(1);
+        let message = Recording::get::<DispatcherNodeQueryResponse>(&recording_arc, 0);
+        assert_eq!(
+            message.result.unwrap(),
+            NodeQueryResponseMetadata::new(
+                another_neighbor_a.public_key().clone(),
+                Some(another_neighbor_a.node_addr_opt().unwrap().clone()),
+                another_neighbor_a.rate_pack().clone(),
+            )
+        );
+        assert_eq!(message.context, context_a);
     }
 
     #[test]
-    fn neighborhood_does_not_update_past_neighbors_when_neighbor_list_does_not_change() {
-        let subject_node = make_global_cryptde_node_record(5555, true); // 9e7p7un06eHs6frl5A
-        let steadfast_neighbor = make_node_record(1111, true);
-        let mut subject: Neighborhood =
-            neighborhood_from_nodes(&subject_node, Some(&steadfast_neighbor));
-        subject
-            .neighborhood_database
-            .add_node(steadfast_neighbor.clone())
+    fn neighborhood_sends_node_query_response_with_none_when_ip_address_query_matches_no_configured_data(
+    ) {
+        let cryptde: &dyn CryptDE = main_cryptde();
+        let earning_wallet = make_wallet("earning");
+        let consuming_wallet = Some(make_paying_wallet(b"consuming"));
+        let (recorder, awaiter, recording_arc) = make_recorder();
+        thread::spawn(move || {
+            let system = System::new("neighborhood_sends_node_query_response_with_none_when_ip_address_query_matches_no_configured_data");
+            let addr: Addr<Recorder> = recorder.start();
+            let recipient: Recipient<DispatcherNodeQueryResponse> =
+                addr.recipient::<DispatcherNodeQueryResponse>();
+            let subject = Neighborhood::new(
+                cryptde,
+                &bc_from_nc_plus(
+                    NeighborhoodConfig {
+                        mode: NeighborhoodMode::Standard(
+                            NodeAddr::new(&IpAddr::from_str("5.4.3.2").unwrap(), &[5678]),
+                            vec![NodeDescriptor::from((
+                                &PublicKey::new(&b"booga"[..]),
+                                &NodeAddr::new(
+                                    &IpAddr::from_str("1.2.3.4").unwrap(),
+                                    &[1234, 2345],
+                                ),
+                                DEFAULT_CHAIN_ID == chain_id_from_name(DEFAULT_CHAIN_NAME),
+                                cryptde,
+                            ))],
+                            rate_pack(100),
+                        ),
+                    },
+                    earning_wallet.clone(),
+                    consuming_wallet.clone(),
+                    "neighborhood_sends_node_query_response_with_none_when_ip_address_query_matches_no_configured_data"
+                ),
+            );
+            let addr: Addr<Neighborhood> = subject.start();
+            let sub: Recipient<DispatcherNodeQueryMessage> =
+                addr.recipient::<DispatcherNodeQueryMessage>();
+
+            sub.try_send(DispatcherNodeQueryMessage {
+                query: NodeQueryMessage::IpAddress(IpAddr::from_str("2.3.4.5").unwrap()),
+                context: TransmitDataMsg {
+                    endpoint: Endpoint::Key(cryptde.public_key().clone()),
+                    last_data: false,
+                    sequence_number: None,
+                    data: Vec::new(),
+                    relayed_from_opt: None,
+                },
+                recipient,
+            })
             .unwrap();
-        subject.neighborhood_database.add_arbitrary_full_neighbor(
-            subject_node.public_key(),
-            steadfast_neighbor.public_key(),
-        );
-        let gossip_acceptor = NeighborReplacementGossipAcceptor {
-            new_neighbors: vec![steadfast_neighbor.clone()],
-        };
-        let set_past_neighbors_params_arc = Arc::new(Mutex::new(vec![]));
-        let persistent_config = PersistentConfigurationMock::new()
-            .set_past_neighbors_params(&set_past_neighbors_params_arc);
-        subject.gossip_acceptor = Box::new(gossip_acceptor);
-        subject.persistent_config_opt = Some(Box::new(persistent_config));
 
-        subject.handle_gossip_agrs(vec![], SocketAddr::from_str("1.2.3.4:1234").unwrap());
+            system.run();
+        });
 
-        let set_past_neighbors_params = set_past_neighbors_params_arc.lock().unwrap();
-        assert!(set_past_neighbors_params.is_empty());
+        awaiter.await_message_count(1);
+        let recording = recording_arc.lock().unwrap();
+        assert_eq!(recording.len(), 1);
+        let message = recording.get_record::<DispatcherNodeQueryResponse>(0);
+        assert_eq!(message.result, None);
     }
 
     #[test]
-    fn neighborhood_does_not_updates_past_neighbors_without_password_even_when_neighbor_list_changes(
+    fn neighborhood_sends_node_query_response_with_result_when_ip_address_query_matches_configured_data(
     ) {
-        let subject_node = make_global_cryptde_node_record(5555, true); // 9e7p7un06eHs6frl5A
-        let old_neighbor = make_node_record(1111, true);
-        let new_neighbor = make_node_record(2222, true);
-        let mut subject: Neighborhood = neighborhood_from_nodes(&subject_node, Some(&old_neighbor));
-        subject
-            .neighborhood_database
-            .add_node(old_neighbor.clone())
-            .unwrap();
-        subject
-            .neighborhood_database
-            .add_arbitrary_full_neighbor(subject_node.public_key(), old_neighbor.public_key());
-        let gossip_acceptor = NeighborReplacementGossipAcceptor {
-            new_neighbors: vec![old_neighbor.clone(), new_neighbor.clone()],
+        let cryptde: &dyn CryptDE = main_cryptde();
+        let (recorder, awaiter, recording_arc) = make_recorder();
+        let node_record = make_node_record(1234, true);
+        let another_node_record = make_node_record(2345, true);
+        let another_node_record_a = another_node_record.clone();
+        let context = TransmitDataMsg {
+            endpoint: Endpoint::Key(cryptde.public_key().clone()),
+            last_data: false,
+            sequence_number: None,
+            data: Vec::new(),
+            relayed_from_opt: None,
         };
-        let set_past_neighbors_params_arc = Arc::new(Mutex::new(vec![]));
-        let persistent_config = PersistentConfigurationMock::new()
-            .set_past_neighbors_params(&set_past_neighbors_params_arc);
-        subject.gossip_acceptor = Box::new(gossip_acceptor);
-        subject.persistent_config_opt = Some(Box::new(persistent_config));
-        subject.db_password_opt = None;
+        let context_a = context.clone();
+        thread::spawn(move || {
+            let system = System::new("neighborhood_sends_node_query_response_with_result_when_ip_address_query_matches_configured_data");
+            let addr: Addr<Recorder> = recorder.start();
+            let recipient: Recipient<DispatcherNodeQueryResponse> =
+                addr.recipient::<DispatcherNodeQueryResponse>();
+            let config = bc_from_nc_plus(
+                NeighborhoodConfig {
+                    mode: NeighborhoodMode::Standard(
+                        node_record.node_addr_opt().unwrap(),
+                        vec![NodeDescriptor::from((
+                            &node_record,
+                            DEFAULT_CHAIN_ID == chain_id_from_name(DEFAULT_CHAIN_NAME),
+                            cryptde,
+                        ))],
+                        rate_pack(100),
+                    ),
+                },
+                node_record.earning_wallet(),
+                None,
+                "neighborhood_sends_node_query_response_with_result_when_ip_address_query_matches_configured_data"
+            );
+            let mut subject = Neighborhood::new(cryptde, &config);
+            subject
+                .neighborhood_database
+                .add_node(another_node_record_a)
+                .unwrap();
+            let addr: Addr<Neighborhood> = subject.start();
+            let sub: Recipient<DispatcherNodeQueryMessage> =
+                addr.recipient::<DispatcherNodeQueryMessage>();
 
-        subject.handle_gossip_agrs(vec![], SocketAddr::from_str("1.2.3.4:1234").unwrap());
+            sub.try_send(DispatcherNodeQueryMessage {
+                query: NodeQueryMessage::IpAddress(IpAddr::from_str("2.3.4.5").unwrap()),
+                context,
+                recipient,
+            })
+            .unwrap();
 
-        let set_past_neighbors_params = set_past_neighbors_params_arc.lock().unwrap();
-        assert!(set_past_neighbors_params.is_empty());
+            system.run();
+        });
+
+        awaiter.await_message_count(1);
+        let message = Recording::get::<DispatcherNodeQueryResponse>(&recording_arc, 0);
+
+        assert_eq!(
+            message.result.unwrap(),
+            NodeQueryResponseMetadata::new(
+                another_node_record.public_key().clone(),
+                Some(another_node_record.node_addr_opt().unwrap().clone()),
+                another_node_record.rate_pack().clone(),
+            )
+        );
+        assert_eq!(message.context, context_a);
     }
 
     #[test]
-    fn neighborhood_logs_error_when_past_neighbors_update_fails() {
-        init_test_logging();
+    fn make_round_trip_route_returns_error_when_no_non_next_door_neighbor_found() {
+        // Make a triangle of Nodes
+        let one_next_door_neighbor = make_node_record(3333, true);
+        let another_next_door_neighbor = make_node_record(4444, true);
         let subject_node = make_global_cryptde_node_record(5555, true); // 9e7p7un06eHs6frl5A
-        let old_neighbor = make_node_record(1111, true);
-        let new_neighbor = make_node_record(2222, true);
-        let mut subject: Neighborhood = neighborhood_from_nodes(&subject_node, Some(&old_neighbor));
+        let mut subject = neighborhood_from_nodes(&subject_node, Some(&one_next_door_neighbor));
+
         subject
             .neighborhood_database
-            .add_node(old_neighbor.clone())
+            .add_node(one_next_door_neighbor.clone())
             .unwrap();
         subject
             .neighborhood_database
-            .add_arbitrary_full_neighbor(subject_node.public_key(), old_neighbor.public_key());
-        let gossip_acceptor = NeighborReplacementGossipAcceptor {
-            new_neighbors: vec![old_neighbor.clone(), new_neighbor.clone()],
-        };
-        let persistent_config = PersistentConfigurationMock::new().set_past_neighbors_result(Err(
-            PersistentConfigError::DatabaseError("Booga".to_string()),
-        ));
-        subject.gossip_acceptor = Box::new(gossip_acceptor);
-        subject.persistent_config_opt = Some(Box::new(persistent_config));
+            .add_node(another_next_door_neighbor.clone())
+            .unwrap();
 
-        subject.handle_gossip_agrs(vec![], SocketAddr::from_str("1.2.3.4:1234").unwrap());
+        subject.neighborhood_database.add_arbitrary_full_neighbor(
+            subject_node.public_key(),
+            one_next_door_neighbor.public_key(),
+        );
+        subject.neighborhood_database.add_arbitrary_full_neighbor(
+            subject_node.public_key(),
+            another_next_door_neighbor.public_key(),
+        );
+        subject.neighborhood_database.add_arbitrary_full_neighbor(
+            one_next_door_neighbor.public_key(),
+            another_next_door_neighbor.public_key(),
+        );
 
-        TestLogHandler::new().exists_log_containing("ERROR: Neighborhood: Could not persist immediate-neighbor changes: DatabaseError(\"Booga\")");
+        let minimum_hop_count = 2;
+
+        let result = subject.make_round_trip_route(RouteQueryMessage {
+            target_key_opt: None,
+            target_component: Component::ProxyClient,
+            minimum_hop_count,
+            return_component_opt: Some(Component::ProxyServer),
+            exit_target_opt: None,
+            avoided_keys: vec![],
+        });
+
+        assert_eq!(
+            Err(format!(
+                "Couldn't find any routes: at least {}-hop from {} to ProxyClient at Unknown",
+                minimum_hop_count,
+                main_cryptde().public_key()
+            )),
+            result
+        );
     }
 
     #[test]
-    fn neighborhood_sends_from_gossip_producer_when_acceptance_introductions_are_not_provided() {
-        init_test_logging();
-        let subject_node = make_global_cryptde_node_record(5555, true); // 9e7p7un06eHs6frl5A
-        let neighbor = make_node_record(1050, true);
-        let mut subject = neighborhood_from_nodes(&subject_node, Some(&neighbor));
-        let full_neighbor = make_node_record(1234, true);
-        let half_neighbor = make_node_record(2345, true);
+    fn make_round_trip_succeeds_when_it_finds_non_next_door_neighbor_exit_node() {
+        let next_door_neighbor = make_node_record(3333, true);
+        let exit_node = make_node_record(5, false);
+
+        let subject_node = make_global_cryptde_node_record(666, true); // 9e7p7un06eHs6frl5A
+        let mut subject = neighborhood_from_nodes(&subject_node, Some(&next_door_neighbor));
+
         subject
             .neighborhood_database
-            .add_node(full_neighbor.clone())
+            .add_node(next_door_neighbor.clone())
             .unwrap();
         subject
             .neighborhood_database
-            .add_node(half_neighbor.clone())
+            .add_node(exit_node.clone())
             .unwrap();
+
+        subject.neighborhood_database.add_arbitrary_full_neighbor(
+            subject_node.public_key(),
+            next_door_neighbor.public_key(),
+        );
+
         subject
             .neighborhood_database
-            .add_arbitrary_full_neighbor(subject_node.public_key(), full_neighbor.public_key());
-        subject
-            .neighborhood_database
-            .add_arbitrary_half_neighbor(subject_node.public_key(), half_neighbor.public_key());
-        let gossip_acceptor =
-            GossipAcceptorMock::new().handle_result(GossipAcceptanceResult::Accepted);
-        subject.gossip_acceptor = Box::new(gossip_acceptor);
-        let gossip = Gossip_0v1::new(vec![]);
-        let produce_params_arc = Arc::new(Mutex::new(vec![]));
-        let gossip_producer = GossipProducerMock::new()
-            .produce_params(&produce_params_arc)
-            .produce_result(Some(gossip.clone()))
-            .produce_result(Some(gossip.clone()));
-        subject.gossip_producer = Box::new(gossip_producer);
-        let (hopper, _, hopper_recording_arc) = make_recorder();
-        let peer_actors = peer_actors_builder().hopper(hopper).build();
+            .add_arbitrary_full_neighbor(next_door_neighbor.public_key(), exit_node.public_key());
 
-        let system = System::new("");
-        subject.hopper = Some(peer_actors.hopper.from_hopper_client);
+        let minimum_hop_count = 2;
 
-        subject.handle_gossip(
-            Gossip_0v1::new(vec![]),
-            SocketAddr::from_str("1.1.1.1:1111").unwrap(),
-        );
+        let result = subject.make_round_trip_route(RouteQueryMessage {
+            target_key_opt: None,
+            target_component: Component::ProxyClient,
+            minimum_hop_count,
+            return_component_opt: Some(Component::ProxyServer),
+            exit_target_opt: None,
+            avoided_keys: vec![],
+        });
 
-        System::current().stop();
-        system.run();
+        let next_door_neighbor_cryptde =
+            CryptDENull::from(&next_door_neighbor.public_key(), DEFAULT_CHAIN_ID);
+        let exit_node_cryptde = CryptDENull::from(&exit_node.public_key(), DEFAULT_CHAIN_ID);
 
-        let hopper_recording = hopper_recording_arc.lock().unwrap();
-        let package_1 = hopper_recording.get_record::<IncipientCoresPackage>(0);
-        let package_2 = hopper_recording.get_record::<IncipientCoresPackage>(1);
-        assert_eq!(hopper_recording.len(), 2);
-        fn digest(package: IncipientCoresPackage) -> (PublicKey, CryptData) {
-            (
-                package.route.next_hop(main_cryptde()).unwrap().public_key,
-                package.payload,
-            )
+        let hops = result.clone().unwrap().route.hops;
+        let actual_keys: Vec<PublicKey> = match hops.as_slice() {
+            [hop, exit, hop_back, origin, empty, _accounting] => vec![
+                decodex::<LiveHop>(main_cryptde(), hop)
+                    .expect("hop")
+                    .public_key,
+                decodex::<LiveHop>(&next_door_neighbor_cryptde, exit)
+                    .expect("exit")
+                    .public_key,
+                decodex::<LiveHop>(&exit_node_cryptde, hop_back)
+                    .expect("hop_back")
+                    .public_key,
+                decodex::<LiveHop>(&next_door_neighbor_cryptde, origin)
+                    .expect("origin")
+                    .public_key,
+                decodex::<LiveHop>(main_cryptde(), empty)
+                    .expect("empty")
+                    .public_key,
+            ],
+            l => panic!("our match is wrong, real size is {}, {:?}", l.len(), l),
+        };
+        let expected_public_keys = vec![
+            next_door_neighbor.public_key().clone(),
+            exit_node.public_key().clone(),
+            next_door_neighbor.public_key().clone(),
+            subject_node.public_key().clone(),
+            PublicKey::new(b""),
+        ];
+        assert_eq!(expected_public_keys, actual_keys);
+    }
+
+    fn make_neighborhood_with_two_exit_nodes(
+        policy_a: &str,
+        policy_b: &str,
+    ) -> (Neighborhood, PublicKey, PublicKey) {
+        let next_door_neighbor = make_node_record(3333, true);
+        let mut exit_node_a = make_node_record(4, false);
+        exit_node_a.set_exit_policy(ExitPolicy::from_str(policy_a).unwrap());
+        let mut exit_node_b = make_node_record(5, false);
+        exit_node_b.set_exit_policy(ExitPolicy::from_str(policy_b).unwrap());
+        let subject_node = make_global_cryptde_node_record(666, true);
+        let mut subject = neighborhood_from_nodes(&subject_node, Some(&next_door_neighbor));
+        let db = &mut subject.neighborhood_database;
+        db.add_node(next_door_neighbor.clone()).unwrap();
+        db.add_node(exit_node_a.clone()).unwrap();
+        db.add_node(exit_node_b.clone()).unwrap();
+        db.add_arbitrary_full_neighbor(subject_node.public_key(), next_door_neighbor.public_key());
+        db.add_arbitrary_full_neighbor(next_door_neighbor.public_key(), exit_node_a.public_key());
+        db.add_arbitrary_full_neighbor(next_door_neighbor.public_key(), exit_node_b.public_key());
+        (
+            subject,
+            exit_node_a.public_key().clone(),
+            exit_node_b.public_key().clone(),
+        )
+    }
+
+    fn exit_key_of(response: &RouteQueryResponse) -> PublicKey {
+        match &response.expected_services {
+            ExpectedServices::RoundTrip(over, _, _) => match over.last() {
+                Some(ExpectedService::Exit(key, _, _)) => key.clone(),
+                x => panic!("Expected exit service, found {:?}", x),
+            },
+            x => panic!("Expected round trip, found {:?}", x),
         }
-        let digest_set = vec_to_set(vec![digest(package_1.clone()), digest(package_2.clone())]);
-        assert_eq!(
-            vec_to_set(vec![
-                (
-                    full_neighbor.public_key().clone(),
-                    encodex(
-                        main_cryptde(),
-                        full_neighbor.public_key(),
-                        &MessageType::Gossip(gossip.clone().into()),
-                    )
-                    .unwrap()
-                ),
-                (
-                    half_neighbor.public_key().clone(),
-                    encodex(
-                        main_cryptde(),
-                        half_neighbor.public_key(),
-                        &MessageType::Gossip(gossip.into()),
-                    )
-                    .unwrap()
-                ),
-            ]),
-            digest_set
-        );
-        let tlh = TestLogHandler::new();
-        tlh.exists_log_containing(
-            format!(
-                "INFO: Neighborhood: Sending update Gossip about 0 Nodes to Node {}",
-                full_neighbor.public_key()
-            )
-            .as_str(),
+    }
+
+    #[test]
+    fn make_round_trip_route_avoids_exit_nodes_whose_policy_denies_the_target() {
+        let (mut subject, exit_key_a, exit_key_b) =
+            make_neighborhood_with_two_exit_nodes("category:mail", "host:example.com");
+
+        let mail_result = subject.make_round_trip_route(
+            RouteQueryMessage::data_indefinite_route_request(2)
+                .with_exit_target(Some("smtp.example.org".to_string()), 587),
         );
-        tlh.exists_log_containing(
-            format!(
-                "INFO: Neighborhood: Sending update Gossip about 0 Nodes to Node {}",
-                half_neighbor.public_key()
-            )
-            .as_str(),
+        let example_result = subject.make_round_trip_route(
+            RouteQueryMessage::data_indefinite_route_request(2)
+                .with_exit_target(Some("www.example.com".to_string()), 443),
         );
-        let key_as_str = format!("{}", main_cryptde().public_key());
-        tlh.exists_log_containing(&format!("Sent Gossip: digraph db {{ \"src\" [label=\"Gossip From:\\n{}\\n5.5.5.5\"]; \"dest\" [label=\"Gossip To:\\nAQIDBA\\n1.2.3.4\"]; \"src\" -> \"dest\" [arrowhead=empty]; }}", &key_as_str[..8]));
-        tlh.exists_log_containing(&format!("Sent Gossip: digraph db {{ \"src\" [label=\"Gossip From:\\n{}\\n5.5.5.5\"]; \"dest\" [label=\"Gossip To:\\nAgMEBQ\\n2.3.4.5\"]; \"src\" -> \"dest\" [arrowhead=empty]; }}", &key_as_str[..8]));
+
+        assert_eq!(exit_key_of(&mail_result.unwrap()), exit_key_b);
+        assert_eq!(exit_key_of(&example_result.unwrap()), exit_key_a);
     }
 
     #[test]
-    fn neighborhood_sends_no_gossip_when_target_does_not_exist() {
-        let subject_node = make_global_cryptde_node_record(5555, true); // 9e7p7un06eHs6frl5A
-                                                                        // This is ungossippable not because of any attribute of its own, but because the
-                                                                        // GossipProducerMock is set to return None when ordered to target it.
-        let ungossippable = make_node_record(1050, true);
-        let mut subject = neighborhood_from_nodes(&subject_node, Some(&ungossippable));
-        subject
-            .neighborhood_database
-            .add_node(ungossippable.clone())
-            .unwrap();
-        subject
-            .neighborhood_database
-            .add_arbitrary_full_neighbor(subject_node.public_key(), ungossippable.public_key());
-        let gossip_acceptor =
-            GossipAcceptorMock::new().handle_result(GossipAcceptanceResult::Accepted);
-        subject.gossip_acceptor = Box::new(gossip_acceptor);
-        let produce_params_arc = Arc::new(Mutex::new(vec![]));
-        let gossip_producer = GossipProducerMock::new()
-            .produce_params(&produce_params_arc)
-            .produce_result(None);
-        subject.gossip_producer = Box::new(gossip_producer);
-        let (hopper, _, hopper_recording_arc) = make_recorder();
-        let peer_actors = peer_actors_builder().hopper(hopper).build();
+    fn make_round_trip_route_fails_when_every_exit_node_policy_denies_the_target() {
+        let (mut subject, _, _) = make_neighborhood_with_two_exit_nodes("port:25", "category:mail");
 
-        let system = System::new("");
-        subject.hopper = Some(peer_actors.hopper.from_hopper_client);
+        let result = subject.make_round_trip_route(
+            RouteQueryMessage::data_indefinite_route_request(2)
+                .with_exit_target(Some("smtp.example.org".to_string()), 25),
+        );
 
-        subject.handle_gossip(
-            Gossip_0v1::new(vec![]),
-            SocketAddr::from_str("1.1.1.1:1111").unwrap(),
+        assert_eq!(
+            result,
+            Err(format!(
+                "Couldn't find any routes: at least 2-hop from {} to ProxyClient through an exit Node whose policy permits smtp.example.org:25",
+                main_cryptde().public_key()
+            ))
         );
+    }
 
-        System::current().stop();
-        system.run();
+    fn relay_keys_of(response: &RouteQueryResponse) -> Vec<PublicKey> {
+        match &response.expected_services {
+            ExpectedServices::RoundTrip(over, back, _) => over
+                .iter()
+                .chain(back.iter())
+                .filter_map(|service| match service {
+                    ExpectedService::Routing(key, _, _) => Some(key.clone()),
+                    _ => None,
+                })
+                .collect(),
+            x => panic!("Expected round trip, found {:?}", x),
+        }
+    }
+
+    fn make_neighborhood_with_one_exit_node_behind(
+        relay_count: u16,
+    ) -> (Neighborhood, PublicKey, Vec<PublicKey>) {
+        let subject_node = make_global_cryptde_node_record(666, true);
+        let exit_node = make_node_record(5555, false);
+        let relays = (0..relay_count)
+            .map(|n| make_node_record(1000 + n, true))
+            .collect::<Vec<NodeRecord>>();
+        let mut subject = neighborhood_from_nodes(&subject_node, Some(&relays[0]));
+        let db = &mut subject.neighborhood_database;
+        db.add_node(exit_node.clone()).unwrap();
+        relays.iter().for_each(|relay| {
+            db.add_node(relay.clone()).unwrap();
+            db.add_arbitrary_full_neighbor(subject_node.public_key(), relay.public_key());
+            db.add_arbitrary_full_neighbor(relay.public_key(), exit_node.public_key());
+        });
+        (
+            subject,
+            exit_node.public_key().clone(),
+            relays
+                .iter()
+                .map(|relay| relay.public_key().clone())
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn make_round_trip_route_finds_second_route_to_same_exit_through_other_relays() {
+        let (mut subject, exit_key, relay_keys) = make_neighborhood_with_one_exit_node_behind(4);
+        let first = subject
+            .make_round_trip_route(
+                RouteQueryMessage::data_indefinite_route_request(2)
+                    .with_target_key(exit_key.clone()),
+            )
+            .unwrap();
+        let first_relays = relay_keys_of(&first);
+
+        let result = subject.make_round_trip_route(
+            RouteQueryMessage::data_indefinite_route_request(2)
+                .with_target_key(exit_key.clone())
+                .avoiding(first_relays.clone()),
+        );
 
-        let hopper_recording = hopper_recording_arc.lock().unwrap();
-        assert_eq!(hopper_recording.len(), 0);
+        let second = result.unwrap();
+        assert_eq!(exit_key_of(&second), exit_key);
+        let second_relays = relay_keys_of(&second);
+        assert!(!second_relays.is_empty());
+        assert!(second_relays.iter().all(|key| relay_keys.contains(key)));
+        assert!(second_relays.iter().all(|key| !first_relays.contains(key)));
     }
 
     #[test]
-    fn neighborhood_sends_only_relay_gossip_when_gossip_acceptor_relays() {
-        let subject_node = make_global_cryptde_node_record(5555, true); // 9e7p7un06eHs6frl5A
-        let mut subject =
-            neighborhood_from_nodes(&subject_node, Some(&make_node_record(1111, true)));
-        let debut_node = make_node_record(1234, true);
-        let debut_gossip = GossipBuilder::new(&subject.neighborhood_database)
-            .node(subject_node.public_key(), true)
-            .build();
-        let gossip_acceptor =
-            GossipAcceptorMock::new().handle_result(GossipAcceptanceResult::Reply(
-                debut_gossip.clone(),
-                debut_node.public_key().clone(),
-                debut_node.node_addr_opt().unwrap(),
-            ));
-        subject.gossip_acceptor = Box::new(gossip_acceptor);
-        let (hopper, _, hopper_recording_arc) = make_recorder();
-        let peer_actors = peer_actors_builder().hopper(hopper).build();
-        let system = System::new("");
-        subject.hopper_no_lookup = Some(peer_actors.hopper.from_hopper_client_no_lookup);
-        let gossip_source = SocketAddr::from_str("8.6.5.4:8654").unwrap();
+    fn make_round_trip_route_fails_when_no_route_avoids_the_relays_of_another() {
+        let (mut subject, exit_key, relay_keys) = make_neighborhood_with_one_exit_node_behind(1);
 
-        subject.handle_gossip(
-            // In real life this would be Relay Gossip from gossip_source to debut_node.
-            Gossip_0v1::new(vec![]),
-            gossip_source,
+        let result = subject.make_round_trip_route(
+            RouteQueryMessage::data_indefinite_route_request(2)
+                .with_target_key(exit_key)
+                .avoiding(relay_keys),
         );
 
-        System::current().stop();
-        system.run();
-        let hopper_recording = hopper_recording_arc.lock().unwrap();
-        let package = hopper_recording.get_record::<NoLookupIncipientCoresPackage>(0);
-        assert_eq!(1, hopper_recording.len());
-        assert_eq!(debut_node.public_key(), &package.public_key);
         assert_eq!(
-            debut_node.node_addr_opt().as_ref().unwrap(),
-            &package.node_addr
+            result,
+            Err(format!(
+                "Couldn't find any routes: at least 2-hop from {} to ProxyClient without relaying through any of 1 other Nodes",
+                main_cryptde().public_key()
+            ))
         );
-        assert_eq!(
-            debut_gossip,
-            match decodex::<MessageType>(
-                &CryptDENull::from(debut_node.public_key(), DEFAULT_CHAIN_ID),
-                &package.payload,
-            ) {
-                Ok(MessageType::Gossip(vd)) => Gossip_0v1::try_from(vd).unwrap(),
-                x => panic!("Expected Gossip, but found {:?}", x),
+    }
+
+    #[test]
+    fn neighborhood_advertises_configured_exit_policy_in_root_node_record() {
+        let cryptde = main_cryptde();
+        let mut config = bc_from_nc_plus(
+            NeighborhoodConfig {
+                mode: NeighborhoodMode::ZeroHop,
+            },
+            make_wallet("earning"),
+            None,
+            "neighborhood_advertises_configured_exit_policy_in_root_node_record",
+        );
+        config.exit_policy = ExitPolicy::from_str("port:25,host:example.com").unwrap();
+
+        let subject = Neighborhood::new(cryptde, &config);
+
+        let root = subject.neighborhood_database.root();
+        assert_eq!(root.exit_policy(), &config.exit_policy);
+        let advertised =
+            NodeRecordInner_0v1::try_from(GossipNodeRecord::from(root.clone())).unwrap();
+        assert_eq!(advertised.exit_policy, config.exit_policy);
+    }
+
+    fn set_up_key_rotation(
+        endorse: bool,
+    ) -> (Neighborhood, NodeRecord, NodeRecord, AccessibleGossipRecord) {
+        let subject_node = make_global_cryptde_node_record(5555, true);
+        let previous_node = make_node_record(1111, true);
+        let mut current_node = make_node_record(2222, true);
+        let endorsing_cryptde = CryptDENull::from(
+            if endorse {
+                previous_node.public_key()
+            } else {
+                current_node.public_key()
             },
+            DEFAULT_CHAIN_ID,
         );
+        current_node.set_key_succession_opt(Some(KeySuccession {
+            previous_public_key: previous_node.public_key().clone(),
+            endorsement: endorsing_cryptde
+                .sign(&PlainData::new(current_node.public_key().as_slice()))
+                .unwrap(),
+        }));
+        current_node.regenerate_signed_gossip(&CryptDENull::from(
+            current_node.public_key(),
+            DEFAULT_CHAIN_ID,
+        ));
+        let mut subject = neighborhood_from_nodes(&subject_node, None);
+        let db = &mut subject.neighborhood_database;
+        db.add_node(previous_node.clone()).unwrap();
+        db.add_node(current_node.clone()).unwrap();
+        db.add_half_neighbor(previous_node.public_key()).unwrap();
+        subject.gossip_acceptor =
+            Box::new(GossipAcceptorMock::new().handle_result(GossipAcceptanceResult::Ignored));
+        let agr = AccessibleGossipRecord::from(&current_node);
+        (subject, previous_node, current_node, agr)
     }
 
     #[test]
-    fn neighborhood_sends_no_gossip_when_gossip_acceptor_ignores() {
-        let subject_node = make_global_cryptde_node_record(5555, true); // 9e7p7un06eHs6frl5A
-        let neighbor = make_node_record(1111, true);
-        let mut subject = neighborhood_from_nodes(&subject_node, Some(&neighbor));
-        let gossip_acceptor =
-            GossipAcceptorMock::new().handle_result(GossipAcceptanceResult::Ignored);
-        subject.gossip_acceptor = Box::new(gossip_acceptor);
-        let subject_node = subject.neighborhood_database.root().clone();
-        let (hopper, _, hopper_recording_arc) = make_recorder();
-        let peer_actors = peer_actors_builder().hopper(hopper).build();
-        let system = System::new("");
-        subject.hopper = Some(peer_actors.hopper.from_hopper_client);
-
-        subject.handle_gossip(
-            Gossip_0v1::new(vec![]),
-            subject_node.node_addr_opt().unwrap().into(),
+    fn neighborhood_advertises_key_succession_in_root_node_record() {
+        let previous = CryptDEReal::new(DEFAULT_CHAIN_ID);
+        let cryptde: &'static dyn CryptDE = Box::leak(Box::new(
+            CryptDEReal::new(DEFAULT_CHAIN_ID)
+                .succeed(previous, SystemTime::now() + Duration::from_secs(3600)),
+        ));
+        let config = bc_from_nc_plus(
+            NeighborhoodConfig {
+                mode: NeighborhoodMode::ZeroHop,
+            },
+            make_wallet("earning"),
+            None,
+            "neighborhood_advertises_key_succession_in_root_node_record",
         );
 
-        System::current().stop();
-        system.run();
-        let hopper_recording = hopper_recording_arc.lock().unwrap();
-        assert_eq!(0, hopper_recording.len());
+        let subject = Neighborhood::new(cryptde, &config);
+
+        let root = subject.neighborhood_database.root();
+        assert_eq!(
+            root.key_succession_opt(),
+            cryptde.key_succession_opt().as_ref()
+        );
+        let advertised =
+            NodeRecordInner_0v1::try_from(GossipNodeRecord::from(root.clone())).unwrap();
+        assert_eq!(advertised.key_succession_opt, cryptde.key_succession_opt());
     }
 
     #[test]
-    fn neighborhood_complains_about_inability_to_ban_when_gossip_acceptor_requests_it() {
-        init_test_logging();
-        let subject_node = make_global_cryptde_node_record(5555, true); // 9e7p7un06eHs6frl5A
-        let neighbor = make_node_record(1111, true);
-        let mut subject = neighborhood_from_nodes(&subject_node, Some(&neighbor));
-        let gossip_acceptor = GossipAcceptorMock::new()
-            .handle_result(GossipAcceptanceResult::Ban("Bad guy".to_string()));
-        subject.gossip_acceptor = Box::new(gossip_acceptor);
-        let subject_node = subject.neighborhood_database.root().clone();
-        let (hopper, _, hopper_recording_arc) = make_recorder();
-        let peer_actors = peer_actors_builder().hopper(hopper).build();
-        let system = System::new("");
-        subject.hopper = Some(peer_actors.hopper.from_hopper_client);
-
-        subject.handle_gossip(
-            Gossip_0v1::new(vec![]),
-            subject_node.node_addr_opt().unwrap().into(),
+    fn neighborhood_advertises_cipher_suites_in_root_node_record() {
+        let cryptde: &'static dyn CryptDE = Box::leak(Box::new(CryptDEReal::new(DEFAULT_CHAIN_ID)));
+        let config = bc_from_nc_plus(
+            NeighborhoodConfig {
+                mode: NeighborhoodMode::ZeroHop,
+            },
+            make_wallet("earning"),
+            None,
+            "neighborhood_advertises_cipher_suites_in_root_node_record",
         );
 
-        System::current().stop();
-        system.run();
-        let hopper_recording = hopper_recording_arc.lock().unwrap();
-        assert_eq!(0, hopper_recording.len());
-        let tlh = TestLogHandler::new();
-        tlh.exists_log_containing("WARN: Neighborhood: Malefactor detected at 5.5.5.5:5555, but malefactor bans not yet implemented; ignoring: Bad guy");
+        let subject = Neighborhood::new(cryptde, &config);
+
+        let root = subject.neighborhood_database.root();
+        assert_eq!(root.features(), &cipher_suite::local_features());
+        let advertised =
+            NodeRecordInner_0v1::try_from(GossipNodeRecord::from(root.clone())).unwrap();
+        assert_eq!(advertised.features, cipher_suite::local_features());
     }
 
     #[test]
-    fn neighborhood_does_not_accept_gossip_if_a_record_is_non_deserializable() {
-        init_test_logging();
-        let mut subject = make_standard_subject();
-        let gossip_acceptor = GossipAcceptorMock::new();
-        subject.gossip_acceptor = Box::new(gossip_acceptor);
-        let db = &mut subject.neighborhood_database;
-        let one_node_key = &db.add_node(make_node_record(2222, true)).unwrap();
-        let another_node_key = &db.add_node(make_node_record(3333, true)).unwrap();
-        let mut gossip = GossipBuilder::new(db)
-            .node(one_node_key, true)
-            .node(another_node_key, false)
-            .build();
-        gossip.node_records[1].signed_data = PlainData::new(&[1, 2, 3, 4]); // corrupt second record
-        let gossip_source = SocketAddr::from_str("1.2.3.4:1234").unwrap();
+    fn neighborhood_asks_for_the_chosen_masquerade_profiles_in_root_node_record() {
+        let cryptde = main_cryptde();
+        let mut config = bc_from_nc_plus(
+            NeighborhoodConfig {
+                mode: NeighborhoodMode::ZeroHop,
+            },
+            make_wallet("earning"),
+            None,
+            "neighborhood_asks_for_the_chosen_masquerade_profiles_in_root_node_record",
+        );
+        config.masquerade_profiles = vec!["websocket".to_string()];
 
-        subject.handle_gossip(gossip, gossip_source);
+        let subject = Neighborhood::new(cryptde, &config);
 
-        // No panic means that subject didn't try to invoke the GossipAcceptorMock: test passes!
-        TestLogHandler::new().exists_log_containing(&format!(
-            "ERROR: Neighborhood: Received non-deserializable Gossip from {}",
-            gossip_source
-        ));
+        let root = subject.neighborhood_database.root();
+        assert_eq!(
+            root.features()
+                .contains(masquerade_profile::WEBSOCKET_PROFILE),
+            true
+        );
     }
 
     #[test]
-    fn neighborhood_does_not_accept_gossip_if_a_record_signature_is_invalid() {
-        init_test_logging();
-        let mut subject = make_standard_subject();
-        let gossip_acceptor = GossipAcceptorMock::new();
-        subject.gossip_acceptor = Box::new(gossip_acceptor);
-        let db = &mut subject.neighborhood_database;
-        let one_node_key = &db.add_node(make_node_record(2222, true)).unwrap();
-        let another_node_key = &db.add_node(make_node_record(3333, true)).unwrap();
-        let mut gossip = GossipBuilder::new(db)
-            .node(one_node_key, true)
-            .node(another_node_key, false)
-            .build();
-        gossip.node_records[1].signature = CryptData::new(&[1, 2, 3, 4]); // corrupt second record
-        let gossip_source = SocketAddr::from_str("1.2.3.4:1234").unwrap();
+    fn features_of_nodes_in_accepted_gossip_choose_their_cipher_suite() {
+        let subject_node = make_global_cryptde_node_record(5555, true);
+        let mut modern_node = make_node_record(4321, true);
+        modern_node.set_features(cipher_suite::local_features());
+        let unknown_node = make_node_record(4322, true);
+        let mut subject = neighborhood_from_nodes(&subject_node, None);
+        subject
+            .neighborhood_database
+            .add_node(modern_node.clone())
+            .unwrap();
+        subject.gossip_acceptor =
+            Box::new(GossipAcceptorMock::new().handle_result(GossipAcceptanceResult::Ignored));
 
-        subject.handle_gossip(gossip, gossip_source);
+        subject.handle_gossip_agrs(
+            vec![
+                AccessibleGossipRecord::from(&modern_node),
+                AccessibleGossipRecord::from(&unknown_node),
+            ],
+            SocketAddr::from_str("4.3.2.1:4321").unwrap(),
+        );
 
-        // No panic means that subject didn't try to invoke the GossipAcceptorMock: test passes!
-        TestLogHandler::new().exists_log_containing(&format!(
-            "ERROR: Neighborhood: Received Gossip with invalid signature from {}",
-            gossip_source
-        ));
+        assert_eq!(
+            cipher_suite::suite_for(modern_node.public_key()).name(),
+            cipher_suite::X25519_CHACHA20_POLY1305
+        );
+        assert_eq!(
+            cipher_suite::suite_for(unknown_node.public_key()).name(),
+            cipher_suite::X25519_XSALSA20_POLY1305
+        );
     }
 
     #[test]
-    fn neighborhood_logs_received_gossip_in_dot_graph_format() {
-        init_test_logging();
-        let cryptde = main_cryptde();
-        let this_node = NodeRecord::new_for_tests(
-            &cryptde.public_key(),
-            Some(&NodeAddr::new(
-                &IpAddr::from_str("5.4.3.2").unwrap(),
-                &[1234],
-            )),
-            100,
-            true,
-            true,
+    fn kem_keys_of_nodes_in_accepted_gossip_are_recorded() {
+        let subject_node = make_global_cryptde_node_record(5555, true);
+        let mut consenting_node = make_node_record(4323, true);
+        consenting_node.set_kem_public_key_opt(Some(PlainData::new(b"kyber key")));
+        let mut subject = neighborhood_from_nodes(&subject_node, None);
+        subject
+            .neighborhood_database
+            .add_node(consenting_node.clone())
+            .unwrap();
+        subject.gossip_acceptor =
+            Box::new(GossipAcceptorMock::new().handle_result(GossipAcceptanceResult::Ignored));
+
+        subject.handle_gossip_agrs(
+            vec![AccessibleGossipRecord::from(&consenting_node)],
+            SocketAddr::from_str("4.3.2.1:4323").unwrap(),
         );
-        let mut db = db_from_node(&this_node);
-        let far_neighbor = make_node_record(1324, true);
-        let gossip_neighbor = make_node_record(4657, true);
-        db.add_node(far_neighbor.clone()).unwrap();
-        db.add_node(gossip_neighbor.clone()).unwrap();
-        db.add_arbitrary_full_neighbor(this_node.public_key(), gossip_neighbor.public_key());
-        db.add_arbitrary_full_neighbor(gossip_neighbor.public_key(), far_neighbor.public_key());
-        db.node_by_key_mut(this_node.public_key()).unwrap().resign();
-        db.node_by_key_mut(gossip_neighbor.public_key())
-            .unwrap()
-            .resign();
-        db.node_by_key_mut(far_neighbor.public_key())
-            .unwrap()
-            .resign();
 
-        let gossip = GossipBuilder::new(&db)
-            .node(gossip_neighbor.public_key(), true)
-            .node(far_neighbor.public_key(), false)
-            .build();
-        let cores_package = ExpiredCoresPackage {
-            immediate_neighbor: SocketAddr::from_str("1.2.3.4:1234").unwrap(),
-            paying_wallet: Some(make_paying_wallet(b"consuming")),
-            remaining_route: make_meaningless_route(),
-            payload: gossip,
-            payload_len: 0,
-        };
-        let hopper = Recorder::new();
-        let this_node_inside = this_node.clone();
-        thread::spawn(move || {
-            let system = System::new("");
-            let subject = Neighborhood::new(
-                cryptde,
-                &bc_from_nc_plus(
-                    NeighborhoodConfig {
-                        mode: NeighborhoodMode::Standard(
-                            this_node_inside.node_addr_opt().unwrap(),
-                            vec![],
-                            rate_pack(100),
-                        ),
-                    },
-                    this_node_inside.earning_wallet(),
-                    None,
-                    "neighborhood_logs_received_gossip_in_dot_graph_format",
-                ),
-            );
+        assert_eq!(
+            hybrid_kem::peer_kem_key_opt(consenting_node.public_key()),
+            Some(b"kyber key".to_vec())
+        );
+    }
 
-            let addr: Addr<Neighborhood> = subject.start();
-            let peer_actors = peer_actors_builder().hopper(hopper).build();
-            addr.try_send(BindMessage { peer_actors }).unwrap();
+    #[test]
+    fn endorsed_key_succession_replaces_the_previous_key() {
+        init_test_logging();
+        let (mut subject, previous_node, current_node, agr) = set_up_key_rotation(true);
+        let version_before = subject.neighborhood_database.root().version();
 
-            let sub = addr.recipient::<ExpiredCoresPackage<Gossip_0v1>>();
-            sub.try_send(cores_package).unwrap();
+        subject.handle_gossip_agrs(vec![agr], SocketAddr::from_str("2.2.2.2:2222").unwrap());
 
-            system.run();
-        });
-        let tlh = TestLogHandler::new();
-        tlh.await_log_containing(
-            &format!("\"BAYFBw\" [label=\"AR v0\\nBAYFBw\\n4.6.5.7:4657\"];"),
-            5000,
-        );
+        let db = &subject.neighborhood_database;
+        assert_eq!(db.node_by_key(previous_node.public_key()), None);
+        assert!(db.node_by_key(current_node.public_key()).is_some());
+        assert!(!db.root().has_half_neighbor(previous_node.public_key()));
+        assert!(db.root().has_half_neighbor(current_node.public_key()));
+        assert_eq!(db.root().version(), version_before + 1);
+        TestLogHandler::new().exists_log_containing(&format!(
+            "INFO: Neighborhood: Node {} has rotated its key to {}",
+            previous_node.public_key(),
+            current_node.public_key()
+        ));
+    }
 
-        tlh.exists_log_containing("Received Gossip: digraph db { ");
-        tlh.exists_log_containing("\"AQMCBA\" [label=\"AR v0\\nAQMCBA\"];");
-        tlh.exists_log_containing(&format!(
-            "\"{}\" [label=\"{}\"] [shape=none];",
-            cryptde.public_key(),
-            &format!("{}", cryptde.public_key())[..8]
+    #[test]
+    fn unendorsed_key_succession_is_ignored() {
+        init_test_logging();
+        let (mut subject, previous_node, current_node, agr) = set_up_key_rotation(false);
+
+        subject.handle_gossip_agrs(vec![agr], SocketAddr::from_str("2.2.2.2:2222").unwrap());
+
+        let db = &subject.neighborhood_database;
+        assert!(db.node_by_key(previous_node.public_key()).is_some());
+        assert!(db.root().has_half_neighbor(previous_node.public_key()));
+        assert!(!db.root().has_half_neighbor(current_node.public_key()));
+        TestLogHandler::new().exists_log_containing(&format!(
+            "WARN: Neighborhood: Node {} claims to succeed {}, but the endorsement is not valid; ignoring",
+            current_node.public_key(),
+            previous_node.public_key()
         ));
-        tlh.exists_log_containing(&format!("\"BAYFBw\" -> \"{}\";", cryptde.public_key()));
-        tlh.exists_log_containing("\"AQMCBA\" -> \"BAYFBw\";");
-        tlh.exists_log_containing("\"BAYFBw\" -> \"AQMCBA\";");
+    }
+
+    fn make_wallet_change(
+        previous_wallet: &Wallet,
+        current_wallet: &Wallet,
+        signer: &Wallet,
+    ) -> WalletChange {
+        WalletChange {
+            previous_wallet: previous_wallet.as_address_wallet(),
+            effective_block: 1234,
+            signature: sign_message(
+                &WalletChange::message(previous_wallet, current_wallet),
+                signer,
+            )
+            .unwrap(),
+        }
     }
 
     #[test]
-    fn node_gossips_to_neighbors_on_startup() {
-        let cryptde: &dyn CryptDE = main_cryptde();
-        let neighbor = make_node_record(1234, true);
-        let (hopper, _, hopper_recording) = make_recorder();
-        let neighbor_inside = neighbor.clone();
-        let subject = Neighborhood::new(
-            cryptde,
-            &bc_from_nc_plus(
-                NeighborhoodConfig {
-                    mode: NeighborhoodMode::Standard(
-                        NodeAddr::new(&IpAddr::from_str("5.4.3.2").unwrap(), &[1234]),
-                        vec![NodeDescriptor::from((
-                            &neighbor_inside,
-                            DEFAULT_CHAIN_ID == chain_id_from_name(DEFAULT_CHAIN_NAME),
-                            cryptde,
-                        ))],
-                        rate_pack(100),
-                    ),
-                },
-                NodeRecord::earning_wallet_from_key(&cryptde.public_key()),
-                NodeRecord::consuming_wallet_from_key(&cryptde.public_key()),
-                "node_gossips_to_neighbors_on_startup",
-            ),
+    fn neighborhood_advertises_earning_wallet_change_in_root_node_record() {
+        let cryptde = main_cryptde();
+        let mut config = bc_from_nc_plus(
+            NeighborhoodConfig {
+                mode: NeighborhoodMode::ZeroHop,
+            },
+            make_wallet("earning"),
+            None,
+            "neighborhood_advertises_earning_wallet_change_in_root_node_record",
         );
-        let this_node = subject.neighborhood_database.root().clone();
-        let system = System::new("node_gossips_to_neighbors_on_startup");
-        let addr: Addr<Neighborhood> = subject.start();
-        let peer_actors = peer_actors_builder().hopper(hopper).build();
-        addr.try_send(BindMessage { peer_actors }).unwrap();
+        let previous_wallet = make_paying_wallet(b"previous");
+        let wallet_change =
+            make_wallet_change(&previous_wallet, &make_wallet("earning"), &previous_wallet);
+        config.earning_wallet_change_opt = Some(wallet_change.clone());
+
+        let subject = Neighborhood::new(cryptde, &config);
+
+        let root = subject.neighborhood_database.root();
+        assert_eq!(root.wallet_change_opt(), Some(&wallet_change));
+        assert!(wallet_change.is_signed(&make_wallet("earning")));
+        let advertised =
+            NodeRecordInner_0v1::try_from(GossipNodeRecord::from(root.clone())).unwrap();
+        assert_eq!(advertised.wallet_change_opt.as_ref(), Some(&wallet_change));
+    }
+
+    // changed_node starts out in the database earning into wallet_before, then Gossips a record
+    // that earns into a new wallet instead, with wallet_change
+    fn set_up_wallet_change(
+        wallet_before: &Wallet,
+        wallet_change: impl FnOnce(&Wallet) -> WalletChange,
+    ) -> (Neighborhood, NodeRecord, AccessibleGossipRecord) {
+        let subject_node = make_global_cryptde_node_record(5555, true);
+        let mut changed_node = make_node_record(3333, true);
+        let node_cryptde = CryptDENull::from(changed_node.public_key(), DEFAULT_CHAIN_ID);
+        changed_node.set_earning_wallet(wallet_before.as_address_wallet());
+        changed_node.regenerate_signed_gossip(&node_cryptde);
+        let mut subject = neighborhood_from_nodes(&subject_node, None);
+        subject
+            .neighborhood_database
+            .add_node(changed_node.clone())
+            .unwrap();
+        let current_wallet = make_wallet("current");
+        changed_node.set_earning_wallet(current_wallet.clone());
+        changed_node.set_wallet_change_opt(Some(wallet_change(&current_wallet)));
+        changed_node.increment_version();
+        changed_node.regenerate_signed_gossip(&node_cryptde);
+        subject.gossip_acceptor = Box::new(
+            GossipAcceptorMock::new()
+                .apply_records()
+                .handle_result(GossipAcceptanceResult::Ignored)
+                .handle_result(GossipAcceptanceResult::Ignored),
+        );
+        let agr = AccessibleGossipRecord::from(&changed_node);
+        (subject, changed_node, agr)
+    }
 
-        let sub = addr.recipient::<StartMessage>();
+    fn reports_to_accountant(
+        subject: &mut Neighborhood,
+        agrs: Vec<Vec<AccessibleGossipRecord>>,
+    ) -> Vec<ReportEarningWalletChangeMessage> {
+        let (accountant, _, accountant_recording_arc) = make_recorder();
+        let peer_actors = peer_actors_builder().accountant(accountant).build();
+        let system = System::new("reports_to_accountant");
+        subject.report_earning_wallet_change_sub =
+            Some(peer_actors.accountant.report_earning_wallet_change);
+
+        agrs.into_iter().for_each(|agrs| {
+            subject.handle_gossip_agrs(agrs, SocketAddr::from_str("3.3.3.3:3333").unwrap())
+        });
 
-        sub.try_send(StartMessage {}).unwrap();
         System::current().stop();
         system.run();
-        let locked_recording = hopper_recording.lock().unwrap();
-        let package_ref: &NoLookupIncipientCoresPackage = locked_recording.get_record(0);
-        let neighbor_node_cryptde = CryptDENull::from(neighbor.public_key(), DEFAULT_CHAIN_ID);
-        let decrypted_payload = neighbor_node_cryptde.decode(&package_ref.payload).unwrap();
-        let gossip = match serde_cbor::de::from_slice(decrypted_payload.as_slice()).unwrap() {
-            MessageType::Gossip(vd) => Gossip_0v1::try_from(vd).unwrap(),
-            x => panic!("Should have been MessageType::Gossip, but was {:?}", x),
-        };
-        let temp_db = db_from_node(&this_node);
-        let expected_gnr = GossipNodeRecord::from((&temp_db, this_node.public_key(), true));
-        assert_contains(&gossip.node_records, &expected_gnr);
-        assert_eq!(1, gossip.node_records.len());
+        let accountant_recording = accountant_recording_arc.lock().unwrap();
+        (0..accountant_recording.len())
+            .map(|index| {
+                accountant_recording
+                    .get_record::<ReportEarningWalletChangeMessage>(index)
+                    .clone()
+            })
+            .collect()
     }
 
-    /*
-            Database, where we'll fail to make a three-hop route to C after removing A:
+    #[test]
+    fn wallet_change_signed_by_the_old_wallet_is_reported_to_the_accountant_once() {
+        init_test_logging();
+        let previous_wallet = make_paying_wallet(b"previous");
+        let (mut subject, changed_node, agr) =
+            set_up_wallet_change(&previous_wallet, |current_wallet| {
+                make_wallet_change(&previous_wallet, current_wallet, &previous_wallet)
+            });
 
-                 NN--------+
-                 NN        |
-                 |         |
-                 v         v
-                 AA-->BB-->CC
-                 AA<--BB<--CC
+        let reports = reports_to_accountant(&mut subject, vec![vec![agr.clone()], vec![agr]]);
 
-                 after removing A as neighbor...
+        assert_eq!(
+            reports,
+            vec![ReportEarningWalletChangeMessage {
+                previous_wallet: previous_wallet.as_address_wallet(),
+                current_wallet: changed_node.earning_wallet(),
+                effective_block: 1234,
+            }]
+        );
+        TestLogHandler::new().exists_log_containing(&format!(
+            "INFO: Neighborhood: Node {} earns into wallet {} instead of {} as of block 1234",
+            changed_node.public_key(),
+            changed_node.earning_wallet(),
+            previous_wallet
+        ));
+    }
 
-                 NN--------+
-                 NN        |
-                           |
-                           v
-                 AA-->BB-->CC
-                 AA<--BB<--CC
+    #[test]
+    fn wallet_change_signed_by_anything_but_the_old_wallet_is_ignored() {
+        init_test_logging();
+        let previous_wallet = make_paying_wallet(b"previous");
+        let (mut subject, changed_node, agr) =
+            set_up_wallet_change(&previous_wallet, |current_wallet| {
+                make_wallet_change(
+                    &previous_wallet,
+                    current_wallet,
+                    &make_paying_wallet(b"not previous"),
+                )
+            });
 
-            Tests will be written from the viewpoint of N.
-    */
+        let reports = reports_to_accountant(&mut subject, vec![vec![agr]]);
+
+        assert_eq!(reports, vec![]);
+        TestLogHandler::new().exists_log_containing(&format!(
+            "WARN: Neighborhood: Node {} claims to have changed its earning wallet, but the old wallet didn't sign the change; ignoring",
+            changed_node.public_key()
+        ));
+    }
 
     #[test]
-    fn neighborhood_removes_neighbor_when_directed_to() {
-        let system = System::new("neighborhood_removes_neighbor_when_directed_to");
-        let hopper = Recorder::new();
-        let mut subject = make_standard_subject();
-        let n = &subject.neighborhood_database.root().clone();
-        let a = &make_node_record(3456, true);
-        let b = &make_node_record(4567, false);
-        let c = &make_node_record(5678, true);
-        {
-            let db = &mut subject.neighborhood_database;
-            db.add_node(a.clone()).unwrap();
-            db.add_node(b.clone()).unwrap();
-            db.add_node(c.clone()).unwrap();
-            let mut single_edge = |a: &NodeRecord, b: &NodeRecord| {
-                db.add_arbitrary_half_neighbor(a.public_key(), b.public_key())
-            };
-            single_edge(n, a);
-            single_edge(n, c);
-            single_edge(a, b);
-            single_edge(b, a);
-            single_edge(b, c);
-            single_edge(c, b);
-        }
+    fn a_node_cannot_move_what_is_owed_to_another_nodes_wallet() {
+        init_test_logging();
+        let thief_wallet = make_paying_wallet(b"thief");
+        let victim_wallet = make_paying_wallet(b"victim");
+        let (mut subject, thief_node, agr) =
+            set_up_wallet_change(&thief_wallet, |current_wallet| {
+                make_wallet_change(&victim_wallet, current_wallet, &victim_wallet)
+            });
+        let mut victim_node = make_node_record(4444, true);
+        victim_node.set_earning_wallet(victim_wallet.as_address_wallet());
+        victim_node.regenerate_signed_gossip(&CryptDENull::from(
+            victim_node.public_key(),
+            DEFAULT_CHAIN_ID,
+        ));
+        subject.neighborhood_database.add_node(victim_node).unwrap();
 
-        let addr: Addr<Neighborhood> = subject.start();
-        let peer_actors = peer_actors_builder().hopper(hopper).build();
-        addr.try_send(BindMessage { peer_actors }).unwrap();
+        let reports = reports_to_accountant(&mut subject, vec![vec![agr]]);
 
-        addr.try_send(RemoveNeighborMessage {
-            public_key: a.public_key().clone(),
-        })
-        .unwrap();
+        assert_eq!(reports, vec![]);
+        TestLogHandler::new().exists_log_containing(&format!(
+            "WARN: Neighborhood: Node {} claims to have changed its earning wallet from {}, which it never earned into; ignoring",
+            thief_node.public_key(),
+            victim_wallet
+        ));
+    }
 
-        let three_hop_route_request = RouteQueryMessage {
-            target_key_opt: Some(c.public_key().clone()),
-            target_component: Component::ProxyClient,
-            minimum_hop_count: 3,
-            return_component_opt: None,
-        };
-        let unsuccessful_three_hop_route = addr.send(three_hop_route_request);
-        let public_key_query = addr.send(NodeQueryMessage::PublicKey(a.public_key().clone()));
-        let failed_ip_address_query = addr.send(NodeQueryMessage::IpAddress(
-            a.node_addr_opt().unwrap().ip_addr(),
+    #[test]
+    fn a_neighbor_earning_into_another_wallet_than_the_one_that_signed_its_descriptor_is_refused() {
+        init_test_logging();
+        let subject_node = make_global_cryptde_node_record(5555, true);
+        let honest_node = make_node_record(6161, true);
+        let impersonator_node = make_node_record(6262, true);
+        let unsigned_node = make_node_record(6363, true);
+        let mut subject = neighborhood_from_nodes(&subject_node, None);
+        subject.neighbor_signers.insert(
+            honest_node.public_key().clone(),
+            honest_node.earning_wallet(),
+        );
+        subject.neighbor_signers.insert(
+            impersonator_node.public_key().clone(),
+            make_wallet("the expected operator"),
+        );
+        let handle_params_arc = Arc::new(Mutex::new(vec![]));
+        subject.gossip_acceptor = Box::new(
+            GossipAcceptorMock::new()
+                .handle_params(&handle_params_arc)
+                .handle_result(GossipAcceptanceResult::Ignored),
+        );
+        let impersonated_only = vec![AccessibleGossipRecord::from(&impersonator_node)];
+        let gossip = vec![
+            AccessibleGossipRecord::from(&honest_node),
+            AccessibleGossipRecord::from(&impersonator_node),
+            AccessibleGossipRecord::from(&unsigned_node),
+        ];
+        let gossip_source = SocketAddr::from_str("3.3.3.3:3333").unwrap();
+
+        subject.handle_gossip_agrs(impersonated_only, gossip_source);
+        subject.handle_gossip_agrs(gossip, gossip_source);
+
+        let handle_params = handle_params_arc.lock().unwrap();
+        assert_eq!(handle_params.len(), 1);
+        assert_eq!(
+            handle_params[0]
+                .1
+                .iter()
+                .map(|agr| agr.inner.public_key.clone())
+                .collect::<Vec<PublicKey>>(),
+            vec![
+                honest_node.public_key().clone(),
+                unsigned_node.public_key().clone()
+            ]
+        );
+        TestLogHandler::new().exists_log_containing(&format!(
+            "WARN: Neighborhood: Node {} earns into wallet {}, but its descriptor was signed by {}; it may be an impersonation, so its record is refused",
+            impersonator_node.public_key(),
+            impersonator_node.earning_wallet(),
+            make_wallet("the expected operator")
         ));
-        System::current().stop_with_code(0);
+    }
 
-        system.run();
-        assert_eq!(None, unsuccessful_three_hop_route.wait().unwrap());
+    fn make_attesting_node(port: u16, subject: &Wallet, count: usize, sign: bool) -> NodeRecord {
+        let mut node = make_node_record(port, true);
+        let node_cryptde = CryptDENull::from(node.public_key(), DEFAULT_CHAIN_ID);
+        let signing_cryptde = if sign {
+            node_cryptde.clone()
+        } else {
+            CryptDENull::from(main_cryptde().public_key(), DEFAULT_CHAIN_ID)
+        };
+        let attestor = node.earning_wallet();
+        let attestations = (0..count)
+            .map(|_| Attestation::new(subject.clone(), 4, 0, &attestor, &signing_cryptde))
+            .collect();
+        node.set_attestations(attestations);
+        node.regenerate_signed_gossip(&node_cryptde);
+        node
+    }
+
+    #[test]
+    fn only_signed_attestations_in_moderation_are_taken_and_only_when_heeded() {
+        init_test_logging();
+        let subject_node = make_global_cryptde_node_record(5555, true);
+        let vouched = make_wallet("vouched by gossip");
+        let forged = make_wallet("forged in gossip");
+        let flooded = make_wallet("flooded in gossip");
+        let unheeded = make_wallet("unheeded in gossip");
+        let honest_node = make_attesting_node(6161, &vouched, 1, true);
+        let forging_node = make_attesting_node(6262, &forged, 1, false);
+        let flooding_node =
+            make_attesting_node(6363, &flooded, MAX_PUBLISHED_ATTESTATIONS + 1, true);
+        let unheeded_node = make_attesting_node(6464, &unheeded, 1, true);
+        let mut subject = neighborhood_from_nodes(&subject_node, None);
+        vec![&honest_node, &forging_node, &flooding_node, &unheeded_node]
+            .into_iter()
+            .for_each(|node| {
+                subject
+                    .neighborhood_database
+                    .add_node(node.clone())
+                    .unwrap();
+            });
+        subject.gossip_acceptor = Box::new(
+            GossipAcceptorMock::new()
+                .handle_result(GossipAcceptanceResult::Ignored)
+                .handle_result(GossipAcceptanceResult::Ignored),
+        );
+        let gossip_source = SocketAddr::from_str("3.3.3.3:3333").unwrap();
+
+        subject.handle_gossip_agrs(
+            vec![AccessibleGossipRecord::from(&unheeded_node)],
+            gossip_source,
+        );
+        subject.heed_attestations = true;
+        subject.handle_gossip_agrs(
+            vec![
+                AccessibleGossipRecord::from(&honest_node),
+                AccessibleGossipRecord::from(&forging_node),
+                AccessibleGossipRecord::from(&flooding_node),
+            ],
+            gossip_source,
+        );
+
+        assert_eq!(ATTESTATION_CACHE.attested_payments(&vouched).attestors, 1);
         assert_eq!(
-            a.public_key(),
-            &public_key_query.wait().unwrap().unwrap().public_key
+            ATTESTATION_CACHE.attested_payments(&vouched).paid_on_time,
+            4
         );
-        assert_eq!(None, failed_ip_address_query.wait().unwrap());
+        assert_eq!(ATTESTATION_CACHE.attested_payments(&forged).attestors, 0);
+        assert_eq!(ATTESTATION_CACHE.attested_payments(&flooded).attestors, 0);
+        assert_eq!(ATTESTATION_CACHE.attested_payments(&unheeded).attestors, 0);
+        let tlh = TestLogHandler::new();
+        tlh.exists_log_containing(&format!(
+            "WARN: Neighborhood: Node {} published attestations whose signatures are not valid; ignoring them",
+            forging_node.public_key()
+        ));
+        tlh.exists_log_containing(&format!(
+            "WARN: Neighborhood: Node {} published 17 attestations, more than the 16 allowed; ignoring them",
+            flooding_node.public_key()
+        ));
     }
 
-    fn node_record_to_neighbor_config(node_record_ref: &NodeRecord) -> NodeDescriptor {
-        let cryptde: &dyn CryptDE = main_cryptde();
-        NodeDescriptor::from((
-            &node_record_ref.public_key().clone(),
-            &node_record_ref.node_addr_opt().unwrap().clone(),
-            DEFAULT_CHAIN_ID == chain_id_from_name(DEFAULT_CHAIN_NAME),
-            cryptde,
-        ))
+    #[test]
+    fn attesting_to_payments_publishes_the_payment_records_in_the_root_node_record() {
+        let subject_node = make_global_cryptde_node_record(5555, true);
+        let payer = make_wallet("prolific payer");
+        TRUST_CACHE.insert(
+            &payer,
+            TrustRecord {
+                payments_on_time: 1_000_000,
+                payments_overdue: 3,
+                ..TrustRecord::default()
+            },
+        );
+        let mut subject = neighborhood_from_nodes(&subject_node, None);
+        let version_before = subject.neighborhood_database.root().version();
+
+        subject.attest_to_payments();
+
+        let root = subject.neighborhood_database.root();
+        let attestation = &root.attestations()[0];
+        assert_eq!(attestation.subject, payer);
+        assert_eq!(
+            (attestation.paid_on_time, attestation.overdue),
+            (1_000_000, 3)
+        );
+        assert!(attestation.is_signed(main_cryptde(), root.public_key(), &root.earning_wallet()));
+        assert_eq!(root.version(), version_before + 1);
     }
 
     #[test]
-    fn neighborhood_sends_node_query_response_with_none_when_initially_configured_with_no_data() {
-        let cryptde = main_cryptde();
-        let (recorder, awaiter, recording_arc) = make_recorder();
-        thread::spawn(move || {
-            let system = System::new("responds_with_none_when_initially_configured_with_no_data");
+    fn a_node_found_on_the_local_network_gets_a_debut_at_the_address_it_was_heard_from() {
+        init_test_logging();
+        let subject_node = make_global_cryptde_node_record(5555, true);
+        let neighbor = make_node_record(1050, true);
+        let lan_node = make_node_record(2345, true);
+        let mut subject = neighborhood_from_nodes(&subject_node, Some(&neighbor));
+        let descriptor = NodeDescriptor::from((
+            &lan_node,
+            subject.is_mainnet(),
+            main_cryptde() as &dyn CryptDE,
+        ))
+        .to_string(main_cryptde());
+        let (hopper, _, hopper_recording_arc) = make_recorder();
+        let peer_actors = peer_actors_builder().hopper(hopper).build();
+        let system = System::new("");
+        subject.hopper_no_lookup = Some(peer_actors.hopper.from_hopper_client_no_lookup);
+        let source = IpAddr::from_str("192.168.0.23").unwrap();
 
-            let addr: Addr<Recorder> = recorder.start();
-            let recipient: Recipient<DispatcherNodeQueryResponse> =
-                addr.recipient::<DispatcherNodeQueryResponse>();
+        subject.handle_local_node_discovered(LocalNodeDiscovered { descriptor, source });
 
-            let subject = make_standard_subject();
-            let addr: Addr<Neighborhood> = subject.start();
-            let sub: Recipient<DispatcherNodeQueryMessage> =
-                addr.recipient::<DispatcherNodeQueryMessage>();
+        System::current().stop();
+        system.run();
+        let hopper_recording = hopper_recording_arc.lock().unwrap();
+        let package = hopper_recording.get_record::<NoLookupIncipientCoresPackage>(0);
+        assert_eq!(hopper_recording.len(), 1);
+        assert_eq!(&package.public_key, lan_node.public_key());
+        let local_node_addr = NodeAddr::new(&source, &lan_node.node_addr_opt().unwrap().ports());
+        assert_eq!(package.node_addr, local_node_addr);
+        TestLogHandler::new().exists_log_containing(&format!(
+            "INFO: Neighborhood: Found a Node on the local network at {}; introducing this one",
+            local_node_addr
+        ));
+    }
 
-            sub.try_send(DispatcherNodeQueryMessage {
-                query: NodeQueryMessage::PublicKey(PublicKey::new(&b"booga"[..])),
-                context: TransmitDataMsg {
-                    endpoint: Endpoint::Key(cryptde.public_key().clone()),
-                    last_data: false,
-                    sequence_number: None,
-                    data: Vec::new(),
-                },
-                recipient,
-            })
+    #[test]
+    fn a_node_found_on_the_local_network_is_left_alone_if_it_is_known_foreign_or_this_one() {
+        let subject_node = make_global_cryptde_node_record(5555, true);
+        let neighbor = make_node_record(1050, true);
+        let known_node = make_node_record(2345, true);
+        let mut subject = neighborhood_from_nodes(&subject_node, Some(&neighbor));
+        subject
+            .neighborhood_database
+            .add_node(known_node.clone())
             .unwrap();
-
-            system.run();
+        let mainnet = subject.is_mainnet();
+        let descriptor_of = |node: &NodeRecord, mainnet: bool| {
+            NodeDescriptor::from((node, mainnet, main_cryptde() as &dyn CryptDE))
+                .to_string(main_cryptde())
+        };
+        let (hopper, _, hopper_recording_arc) = make_recorder();
+        let peer_actors = peer_actors_builder().hopper(hopper).build();
+        let system = System::new("");
+        subject.hopper_no_lookup = Some(peer_actors.hopper.from_hopper_client_no_lookup);
+        let source = IpAddr::from_str("192.168.0.23").unwrap();
+
+        vec![
+            descriptor_of(&known_node, mainnet),
+            descriptor_of(&make_node_record(3456, true), !mainnet),
+            descriptor_of(&subject_node, mainnet),
+            "booga".to_string(),
+        ]
+        .into_iter()
+        .for_each(|descriptor| {
+            subject.handle_local_node_discovered(LocalNodeDiscovered { descriptor, source })
         });
 
-        awaiter.await_message_count(1);
-        let recording = recording_arc.lock().unwrap();
-        assert_eq!(recording.len(), 1);
-        let message = recording.get_record::<DispatcherNodeQueryResponse>(0);
-        assert_eq!(message.result, None);
+        System::current().stop();
+        system.run();
+        let hopper_recording = hopper_recording_arc.lock().unwrap();
+        assert_eq!(hopper_recording.len(), 0);
     }
 
     #[test]
-    fn neighborhood_sends_node_query_response_with_none_when_key_query_matches_no_configured_data()
-    {
-        let cryptde: &dyn CryptDE = main_cryptde();
-        let earning_wallet = make_wallet("earning");
-        let consuming_wallet = Some(make_paying_wallet(b"consuming"));
-        let (recorder, awaiter, recording_arc) = make_recorder();
-        thread::spawn(move || {
-            let system = System::new("neighborhood_sends_node_query_response_with_none_when_key_query_matches_no_configured_data");
-            let addr: Addr<Recorder> = recorder.start();
-            let recipient: Recipient<DispatcherNodeQueryResponse> =
-                addr.recipient::<DispatcherNodeQueryResponse>();
+    fn only_a_node_that_routes_data_with_lists_to_ask_checks_its_reputation() {
+        let subject_node = make_global_cryptde_node_record(5555, true);
+        let neighbor = make_node_record(1050, true);
+        let mut standard = neighborhood_from_nodes(&subject_node, Some(&neighbor));
+        let mut zero_hop = neighborhood_from_nodes(&subject_node, None);
+        let without_lists = neighborhood_from_nodes(&subject_node, Some(&neighbor));
+        standard.reputation_lists = vec!["zen.example.org".to_string()];
+        zero_hop.reputation_lists = vec!["zen.example.org".to_string()];
 
-            let subject = Neighborhood::new(
-                cryptde,
-                &bc_from_nc_plus(
-                    NeighborhoodConfig {
-                        mode: NeighborhoodMode::Standard(
-                            NodeAddr::new(&IpAddr::from_str("5.4.3.2").unwrap(), &[5678]),
-                            vec![NodeDescriptor::from((
-                                &PublicKey::new(&b"booga"[..]),
-                                &NodeAddr::new(
-                                    &IpAddr::from_str("1.2.3.4").unwrap(),
-                                    &[1234, 2345],
-                                ),
-                                DEFAULT_CHAIN_ID == chain_id_from_name(DEFAULT_CHAIN_NAME),
-                                cryptde,
-                            ))],
-                            rate_pack(100),
-                        ),
-                    },
-                    earning_wallet.clone(),
-                    consuming_wallet.clone(),
-                    "neighborhood_sends_node_query_response_with_none_when_key_query_matches_no_configured_data"
-                ),
-            );
-            let addr: Addr<Neighborhood> = subject.start();
-            let sub: Recipient<DispatcherNodeQueryMessage> =
-                addr.recipient::<DispatcherNodeQueryMessage>();
+        assert_eq!(standard.checks_reputation(), true);
+        assert_eq!(zero_hop.checks_reputation(), false);
+        assert_eq!(without_lists.checks_reputation(), false);
+    }
 
-            sub.try_send(DispatcherNodeQueryMessage {
-                query: NodeQueryMessage::PublicKey(PublicKey::new(&b"blah"[..])),
-                context: TransmitDataMsg {
-                    endpoint: Endpoint::Key(cryptde.public_key().clone()),
-                    last_data: false,
-                    sequence_number: None,
-                    data: Vec::new(),
-                },
-                recipient,
-            })
-            .unwrap();
+    #[test]
+    fn a_listed_ip_address_is_warned_about_and_its_score_advertised() {
+        init_test_logging();
+        let subject_node = make_global_cryptde_node_record(5555, true);
+        let neighbor = make_node_record(1050, true);
+        let mut subject = neighborhood_from_nodes(&subject_node, Some(&neighbor));
+        let version_before = subject.neighborhood_database.root().version();
+        let ip_addr = IpAddr::from_str("1.2.3.4").unwrap();
 
-            system.run();
+        subject.handle_reputation_report(ReputationReport {
+            ip_addr,
+            score: 50,
+            listed_by: vec!["bad.example.org".to_string()],
         });
 
-        awaiter.await_message_count(1);
-        let recording = recording_arc.lock().unwrap();
-        assert_eq!(recording.len(), 1);
-        let message = recording.get_record::<DispatcherNodeQueryResponse>(0);
-        assert_eq!(message.result, None);
+        let root = subject.neighborhood_database.root();
+        assert_eq!(root.reputation_score_opt(), Some(50));
+        assert_eq!(root.version(), version_before + 1);
+        let advertised =
+            NodeRecordInner_0v1::try_from(GossipNodeRecord::from(root.clone())).unwrap();
+        assert_eq!(advertised.reputation_score_opt, Some(50));
+        TestLogHandler::new().exists_log_containing(
+            "WARN: Neighborhood: Your IP address 1.2.3.4 is listed by bad.example.org; sites may refuse or challenge consumers you exit for",
+        );
     }
 
     #[test]
-    fn neighborhood_sends_node_query_response_with_result_when_key_query_matches_configured_data() {
-        let cryptde = main_cryptde();
-        let earning_wallet = make_wallet("earning");
-        let consuming_wallet = Some(make_paying_wallet(b"consuming"));
-        let (recorder, awaiter, recording_arc) = make_recorder();
-        let one_neighbor = make_node_record(2345, true);
-        let another_neighbor = make_node_record(3456, true);
-        let another_neighbor_a = another_neighbor.clone();
-        let context = TransmitDataMsg {
-            endpoint: Endpoint::Key(cryptde.public_key().clone()),
-            last_data: false,
-            sequence_number: None,
-            data: Vec::new(),
+    fn an_unchanged_clean_reputation_is_neither_warned_about_nor_readvertised() {
+        init_test_logging();
+        let subject_node = make_global_cryptde_node_record(5555, true);
+        let neighbor = make_node_record(1050, true);
+        let mut subject = neighborhood_from_nodes(&subject_node, Some(&neighbor));
+        let report = ReputationReport {
+            ip_addr: IpAddr::from_str("5.6.7.8").unwrap(),
+            score: 100,
+            listed_by: vec![],
         };
-        let context_a = context.clone();
-        thread::spawn(move || {
-            let system = System::new("neighborhood_sends_node_query_response_with_result_when_key_query_matches_configured_data");
-            let addr: Addr<Recorder> = recorder.start();
-            let recipient = addr.recipient::<DispatcherNodeQueryResponse>();
-            let mut subject = Neighborhood::new(
-                cryptde,
-                &bc_from_nc_plus(
-                    NeighborhoodConfig {
-                        mode: NeighborhoodMode::Standard(
-                            NodeAddr::new(&IpAddr::from_str("5.4.3.2").unwrap(), &[5678]),
-                            vec![node_record_to_neighbor_config(&one_neighbor)],
-                            rate_pack(100),
-                        ),
-                    },
-                    earning_wallet.clone(),
-                    consuming_wallet.clone(),
-                    "neighborhood_sends_node_query_response_with_result_when_key_query_matches_configured_data"
-                ),
-            );
-            subject
-                .neighborhood_database
-                .add_node(another_neighbor.clone())
-                .unwrap();
-            let addr: Addr<Neighborhood> = subject.start();
-            let sub: Recipient<DispatcherNodeQueryMessage> =
-                addr.recipient::<DispatcherNodeQueryMessage>();
+        subject.handle_reputation_report(report.clone());
+        let version_before = subject.neighborhood_database.root().version();
 
-            sub.try_send(DispatcherNodeQueryMessage {
-                query: NodeQueryMessage::PublicKey(another_neighbor.public_key().clone()),
-                context,
-                recipient,
-            })
-            .unwrap();
+        subject.handle_reputation_report(report);
 
-            system.run();
+        let root = subject.neighborhood_database.root();
+        assert_eq!(root.reputation_score_opt(), Some(100));
+        assert_eq!(root.version(), version_before);
+        TestLogHandler::new()
+            .exists_log_containing("INFO: Neighborhood: Reputation of 5.6.7.8 is 100 out of 100");
+        TestLogHandler::new().exists_no_log_containing("Your IP address 5.6.7.8 is listed");
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Neighborhood should never get ShutdownStreamMsg about non-clandestine stream"
+    )]
+    fn handle_stream_shutdown_complains_about_non_clandestine_message() {
+        let subject_node = make_global_cryptde_node_record(1345, true);
+        let mut subject = neighborhood_from_nodes(&subject_node, None);
+
+        subject.handle_stream_shutdown_msg(StreamShutdownMsg {
+            peer_addr: SocketAddr::from_str("1.2.3.4:5678").unwrap(),
+            stream_type: RemovedStreamType::NonClandestine(NonClandestineAttributes {
+                reception_port: TLS_PORT,
+                sequence_number: 1234,
+                half_closed: false,
+            }),
+            report_to_counterpart: false,
         });
+    }
 
-        awaiter.await_message_count(1);
-        let message = Recording::get::<DispatcherNodeQueryResponse>(&recording_arc, 0);
-        assert_eq!(
-            message.result.unwrap(),
-            NodeQueryResponseMetadata::new(
-                another_neighbor_a.public_key().clone(),
-                Some(another_neighbor_a.node_addr_opt().unwrap().clone()),
-                another_neighbor_a.rate_pack().clone(),
-            )
+    #[test]
+    fn handle_stream_shutdown_handles_socket_addr_with_unknown_ip() {
+        init_test_logging();
+        let (hopper, _, hopper_recording_arc) = make_recorder();
+        let system = System::new("test");
+        let unrecognized_node = make_node_record(3123, true);
+        let unrecognized_node_addr = unrecognized_node.node_addr_opt().unwrap();
+        let unrecognized_socket_addr = SocketAddr::new(
+            unrecognized_node_addr.ip_addr(),
+            unrecognized_node_addr.ports()[0],
         );
-        assert_eq!(message.context, context_a);
+        let subject_node = make_global_cryptde_node_record(1345, true);
+        let mut subject = neighborhood_from_nodes(&subject_node, None);
+        let peer_actors = peer_actors_builder().hopper(hopper).build();
+        subject.hopper = Some(peer_actors.hopper.from_hopper_client);
+
+        subject.handle_stream_shutdown_msg(StreamShutdownMsg {
+            peer_addr: unrecognized_socket_addr,
+            stream_type: RemovedStreamType::Clandestine,
+            report_to_counterpart: true,
+        });
+
+        System::current().stop_with_code(0);
+        system.run();
+
+        assert_eq!(subject.neighborhood_database.keys().len(), 1);
+        let hopper_recording = hopper_recording_arc.lock().unwrap();
+        assert_eq!(hopper_recording.len(), 0);
+        TestLogHandler::new().exists_log_containing(&format!("WARN: Neighborhood: Received shutdown notification for stream to {}, but no Node with that IP is in the database - ignoring", unrecognized_socket_addr.ip()));
     }
 
     #[test]
-    fn neighborhood_sends_node_query_response_with_none_when_ip_address_query_matches_no_configured_data(
-    ) {
-        let cryptde: &dyn CryptDE = main_cryptde();
-        let earning_wallet = make_wallet("earning");
-        let consuming_wallet = Some(make_paying_wallet(b"consuming"));
-        let (recorder, awaiter, recording_arc) = make_recorder();
-        thread::spawn(move || {
-            let system = System::new("neighborhood_sends_node_query_response_with_none_when_ip_address_query_matches_no_configured_data");
-            let addr: Addr<Recorder> = recorder.start();
-            let recipient: Recipient<DispatcherNodeQueryResponse> =
-                addr.recipient::<DispatcherNodeQueryResponse>();
-            let subject = Neighborhood::new(
-                cryptde,
-                &bc_from_nc_plus(
-                    NeighborhoodConfig {
-                        mode: NeighborhoodMode::Standard(
-                            NodeAddr::new(&IpAddr::from_str("5.4.3.2").unwrap(), &[5678]),
-                            vec![NodeDescriptor::from((
-                                &PublicKey::new(&b"booga"[..]),
-                                &NodeAddr::new(
-                                    &IpAddr::from_str("1.2.3.4").unwrap(),
-                                    &[1234, 2345],
-                                ),
-                                DEFAULT_CHAIN_ID == chain_id_from_name(DEFAULT_CHAIN_NAME),
-                                cryptde,
-                            ))],
-                            rate_pack(100),
-                        ),
-                    },
-                    earning_wallet.clone(),
-                    consuming_wallet.clone(),
-                    "neighborhood_sends_node_query_response_with_none_when_ip_address_query_matches_no_configured_data"
-                ),
-            );
-            let addr: Addr<Neighborhood> = subject.start();
-            let sub: Recipient<DispatcherNodeQueryMessage> =
-                addr.recipient::<DispatcherNodeQueryMessage>();
+    fn handle_stream_shutdown_handles_already_inactive_node() {
+        init_test_logging();
+        let (hopper, _, hopper_recording_arc) = make_recorder();
+        let system = System::new("test");
+        let gossip_neighbor_node = make_node_record(2456, true);
+        let inactive_neighbor_node = make_node_record(3123, true);
+        let inactive_neighbor_node_addr = inactive_neighbor_node.node_addr_opt().unwrap();
+        let inactive_neighbor_node_socket_addr = SocketAddr::new(
+            inactive_neighbor_node_addr.ip_addr(),
+            inactive_neighbor_node_addr.ports()[0],
+        );
+        let subject_node = make_global_cryptde_node_record(1345, true);
+        let mut subject = neighborhood_from_nodes(&subject_node, None);
+        subject
+            .neighborhood_database
+            .add_node(gossip_neighbor_node.clone())
+            .unwrap();
+        subject
+            .neighborhood_database
+            .add_node(inactive_neighbor_node.clone())
+            .unwrap();
+        subject.neighborhood_database.add_arbitrary_full_neighbor(
+            subject_node.public_key(),
+            gossip_neighbor_node.public_key(),
+        );
+        subject.neighborhood_database.add_arbitrary_half_neighbor(
+            inactive_neighbor_node.public_key(),
+            subject_node.public_key(),
+        );
+        let peer_actors = peer_actors_builder().hopper(hopper).build();
+        subject.hopper = Some(peer_actors.hopper.from_hopper_client);
+
+        subject.handle_stream_shutdown_msg(StreamShutdownMsg {
+            peer_addr: inactive_neighbor_node_socket_addr,
+            stream_type: RemovedStreamType::Clandestine,
+            report_to_counterpart: true,
+        });
 
-            sub.try_send(DispatcherNodeQueryMessage {
-                query: NodeQueryMessage::IpAddress(IpAddr::from_str("2.3.4.5").unwrap()),
-                context: TransmitDataMsg {
-                    endpoint: Endpoint::Key(cryptde.public_key().clone()),
-                    last_data: false,
-                    sequence_number: None,
-                    data: Vec::new(),
-                },
-                recipient,
-            })
+        System::current().stop_with_code(0);
+        system.run();
+
+        assert_eq!(subject.neighborhood_database.keys().len(), 3);
+        assert_eq!(
+            subject.neighborhood_database.has_half_neighbor(
+                subject_node.public_key(),
+                inactive_neighbor_node.public_key(),
+            ),
+            false
+        );
+        let hopper_recording = hopper_recording_arc.lock().unwrap();
+        assert_eq!(hopper_recording.len(), 0);
+        TestLogHandler::new().exists_log_containing(&format!("DEBUG: Neighborhood: Received shutdown notification for {} at {}, but that Node is no neighbor - ignoring", inactive_neighbor_node.public_key(), inactive_neighbor_node_socket_addr.ip()));
+    }
+
+    #[test]
+    fn handle_stream_shutdown_handles_existing_socket_addr() {
+        init_test_logging();
+        let (hopper, _, hopper_recording_arc) = make_recorder();
+        let system = System::new("test");
+        let gossip_neighbor_node = make_node_record(2456, true);
+        let shutdown_neighbor_node = make_node_record(3123, true);
+        let shutdown_neighbor_node_addr = shutdown_neighbor_node.node_addr_opt().unwrap();
+        let shutdown_neighbor_node_socket_addr = SocketAddr::new(
+            shutdown_neighbor_node_addr.ip_addr(),
+            shutdown_neighbor_node_addr.ports()[0],
+        );
+        let subject_node = make_global_cryptde_node_record(1345, true);
+        let mut subject = neighborhood_from_nodes(&subject_node, None);
+        subject
+            .neighborhood_database
+            .add_node(gossip_neighbor_node.clone())
+            .unwrap();
+        subject
+            .neighborhood_database
+            .add_node(shutdown_neighbor_node.clone())
             .unwrap();
+        subject.neighborhood_database.add_arbitrary_full_neighbor(
+            subject_node.public_key(),
+            gossip_neighbor_node.public_key(),
+        );
+        subject.neighborhood_database.add_arbitrary_full_neighbor(
+            subject_node.public_key(),
+            shutdown_neighbor_node.public_key(),
+        );
+        let peer_actors = peer_actors_builder().hopper(hopper).build();
+        subject.hopper = Some(peer_actors.hopper.from_hopper_client);
 
-            system.run();
+        subject.handle_stream_shutdown_msg(StreamShutdownMsg {
+            peer_addr: shutdown_neighbor_node_socket_addr,
+            stream_type: RemovedStreamType::Clandestine,
+            report_to_counterpart: true,
         });
 
-        awaiter.await_message_count(1);
-        let recording = recording_arc.lock().unwrap();
-        assert_eq!(recording.len(), 1);
-        let message = recording.get_record::<DispatcherNodeQueryResponse>(0);
-        assert_eq!(message.result, None);
+        System::current().stop_with_code(0);
+        system.run();
+
+        assert_eq!(subject.neighborhood_database.keys().len(), 3);
+        assert_eq!(
+            subject.neighborhood_database.has_half_neighbor(
+                subject_node.public_key(),
+                shutdown_neighbor_node.public_key(),
+            ),
+            false
+        );
+        let hopper_recording = hopper_recording_arc.lock().unwrap();
+        assert_eq!(hopper_recording.len(), 1);
+        TestLogHandler::new().exists_log_containing(&format!(
+            "DEBUG: Neighborhood: Received shutdown notification for {} at {}: removing neighborship",
+            shutdown_neighbor_node.public_key(),
+            shutdown_neighbor_node_socket_addr.ip()
+        ));
     }
 
+    #[should_panic(expected = "0: Received shutdown order from client 1234: shutting down hard")]
     #[test]
-    fn neighborhood_sends_node_query_response_with_result_when_ip_address_query_matches_configured_data(
-    ) {
-        let cryptde: &dyn CryptDE = main_cryptde();
-        let (recorder, awaiter, recording_arc) = make_recorder();
-        let node_record = make_node_record(1234, true);
-        let another_node_record = make_node_record(2345, true);
-        let another_node_record_a = another_node_record.clone();
-        let context = TransmitDataMsg {
-            endpoint: Endpoint::Key(cryptde.public_key().clone()),
-            last_data: false,
-            sequence_number: None,
-            data: Vec::new(),
-        };
-        let context_a = context.clone();
-        thread::spawn(move || {
-            let system = System::new("neighborhood_sends_node_query_response_with_result_when_ip_address_query_matches_configured_data");
-            let addr: Addr<Recorder> = recorder.start();
-            let recipient: Recipient<DispatcherNodeQueryResponse> =
-                addr.recipient::<DispatcherNodeQueryResponse>();
-            let config = bc_from_nc_plus(
+    fn shutdown_instruction_generates_log() {
+        running_test();
+        init_test_logging();
+        let system = System::new("test");
+        let subject = Neighborhood::new(
+            main_cryptde(),
+            &bc_from_nc_plus(
                 NeighborhoodConfig {
-                    mode: NeighborhoodMode::Standard(
-                        node_record.node_addr_opt().unwrap(),
-                        vec![NodeDescriptor::from((
-                            &node_record,
-                            DEFAULT_CHAIN_ID == chain_id_from_name(DEFAULT_CHAIN_NAME),
-                            cryptde,
-                        ))],
-                        rate_pack(100),
-                    ),
+                    mode: NeighborhoodMode::ZeroHop,
                 },
-                node_record.earning_wallet(),
+                make_wallet("earning"),
                 None,
-                "neighborhood_sends_node_query_response_with_result_when_ip_address_query_matches_configured_data"
-            );
-            let mut subject = Neighborhood::new(cryptde, &config);
-            subject
-                .neighborhood_database
-                .add_node(another_node_record_a)
-                .unwrap();
-            let addr: Addr<Neighborhood> = subject.start();
-            let sub: Recipient<DispatcherNodeQueryMessage> =
-                addr.recipient::<DispatcherNodeQueryMessage>();
+                "unexpected_ui_message_is_logged_and_ignored",
+            ),
+        );
+        let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
+        let subject_addr = subject.start();
+        let peer_actors = peer_actors_builder().ui_gateway(ui_gateway).build();
+        subject_addr.try_send(BindMessage { peer_actors }).unwrap();
 
-            sub.try_send(DispatcherNodeQueryMessage {
-                query: NodeQueryMessage::IpAddress(IpAddr::from_str("2.3.4.5").unwrap()),
-                context,
-                recipient,
+        subject_addr
+            .try_send(NodeFromUiMessage {
+                client_id: 1234,
+                body: MessageBody {
+                    opcode: "shutdown".to_string(),
+                    path: Conversation(4321),
+                    payload: Ok("{}".to_string()),
+                },
             })
             .unwrap();
 
-            system.run();
-        });
-
-        awaiter.await_message_count(1);
-        let message = Recording::get::<DispatcherNodeQueryResponse>(&recording_arc, 0);
-
-        assert_eq!(
-            message.result.unwrap(),
-            NodeQueryResponseMetadata::new(
-                another_node_record.public_key().clone(),
-                Some(another_node_record.node_addr_opt().unwrap().clone()),
-                another_node_record.rate_pack().clone(),
-            )
-        );
-        assert_eq!(message.context, context_a);
+        System::current().stop();
+        system.run();
+        let ui_gateway_recording = ui_gateway_recording_arc.lock().unwrap();
+        assert_eq!(ui_gateway_recording.len(), 0);
+        TestLogHandler::new()
+            .exists_log_containing("INFO: Neighborhood: Received shutdown order from client 1234");
     }
 
     #[test]
-    fn make_round_trip_route_returns_error_when_no_non_next_door_neighbor_found() {
-        // Make a triangle of Nodes
-        let one_next_door_neighbor = make_node_record(3333, true);
-        let another_next_door_neighbor = make_node_record(4444, true);
-        let subject_node = make_global_cryptde_node_record(5555, true); // 9e7p7un06eHs6frl5A
-        let mut subject = neighborhood_from_nodes(&subject_node, Some(&one_next_door_neighbor));
-
+    fn farewell_gossip_tells_the_neighbors_this_node_is_a_tombstone() {
+        let mut subject = make_standard_subject();
+        let neighbor = make_node_record(1234, true);
+        let root_key = subject.neighborhood_database.root().public_key().clone();
         subject
             .neighborhood_database
-            .add_node(one_next_door_neighbor.clone())
+            .add_node(neighbor.clone())
             .unwrap();
         subject
             .neighborhood_database
-            .add_node(another_next_door_neighbor.clone())
+            .add_arbitrary_full_neighbor(&root_key, neighbor.public_key());
+        let version_before = subject.neighborhood_database.root().version();
+        let (hopper, _, hopper_recording_arc) = make_recorder();
+        let system = System::new("farewell_gossip_tells_the_neighbors_this_node_is_a_tombstone");
+        let peer_actors = peer_actors_builder().hopper(hopper).build();
+        subject.hopper = Some(peer_actors.hopper.from_hopper_client);
+
+        let result = subject.say_farewell();
+
+        System::current().stop();
+        system.run();
+        assert_eq!(result, true);
+        let hopper_recording = hopper_recording_arc.lock().unwrap();
+        let package = hopper_recording.get_record::<IncipientCoresPackage>(0);
+        let gossip = match decodex::<MessageType>(
+            &CryptDENull::from(neighbor.public_key(), DEFAULT_CHAIN_ID),
+            &package.payload,
+        )
+        .unwrap()
+        {
+            MessageType::Gossip(vd) => Gossip_0v1::try_from(vd).unwrap(),
+            other => panic!("Expected Gossip, got {:?}", other),
+        };
+        let root_agr = gossip
+            .node_records
+            .into_iter()
+            .flat_map(AccessibleGossipRecord::try_from)
+            .find(|agr| agr.inner.public_key == root_key)
             .unwrap();
+        assert_eq!(root_agr.inner.tombstone, true);
+        assert_eq!(root_agr.inner.version, version_before + 1);
+    }
 
-        subject.neighborhood_database.add_arbitrary_full_neighbor(
-            subject_node.public_key(),
-            one_next_door_neighbor.public_key(),
-        );
-        subject.neighborhood_database.add_arbitrary_full_neighbor(
-            subject_node.public_key(),
-            another_next_door_neighbor.public_key(),
-        );
-        subject.neighborhood_database.add_arbitrary_full_neighbor(
-            one_next_door_neighbor.public_key(),
-            another_next_door_neighbor.public_key(),
-        );
+    #[test]
+    fn a_node_with_no_neighbors_has_nobody_to_say_farewell_to() {
+        let mut subject = make_standard_subject();
 
-        let minimum_hop_count = 2;
+        let result = subject.say_farewell();
 
-        let result = subject.make_round_trip_route(RouteQueryMessage {
-            target_key_opt: None,
-            target_component: Component::ProxyClient,
-            minimum_hop_count,
-            return_component_opt: Some(Component::ProxyServer),
-        });
+        assert_eq!(result, false);
+        assert_eq!(subject.neighborhood_database.root().is_tombstone(), false);
+    }
+
+    #[test]
+    fn unexpected_ui_message_is_logged_and_ignored() {
+        init_test_logging();
+        let subject = Neighborhood::new(
+            main_cryptde(),
+            &bc_from_nc_plus(
+                NeighborhoodConfig {
+                    mode: NeighborhoodMode::ZeroHop,
+                },
+                make_wallet("earning"),
+                None,
+                "unexpected_ui_message_is_logged_and_ignored",
+            ),
+        );
+        let system = System::new("test");
+        let subject_addr: Addr<Neighborhood> = subject.start();
+        let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
+        let peer_actors = peer_actors_builder().ui_gateway(ui_gateway).build();
+        subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+
+        subject_addr
+            .try_send(NodeFromUiMessage {
+                client_id: 1234,
+                body: MessageBody {
+                    opcode: "booga".to_string(),
+                    path: FireAndForget,
+                    payload: Ok("{}".to_string()),
+                },
+            })
+            .unwrap();
 
-        assert_eq!(
-            Err(format!(
-                "Couldn't find any routes: at least {}-hop from {} to ProxyClient at Unknown",
-                minimum_hop_count,
-                main_cryptde().public_key()
-            )),
-            result
+        System::current().stop();
+        system.run();
+        let ui_gateway_recording = ui_gateway_recording_arc.lock().unwrap();
+        assert_eq!(ui_gateway_recording.len(), 0);
+        TestLogHandler::new().exists_log_containing(
+            "DEBUG: Neighborhood: Ignoring 'booga' request from client 1234",
         );
     }
 
     #[test]
-    fn make_round_trip_succeeds_when_it_finds_non_next_door_neighbor_exit_node() {
-        let next_door_neighbor = make_node_record(3333, true);
-        let exit_node = make_node_record(5, false);
-
-        let subject_node = make_global_cryptde_node_record(666, true); // 9e7p7un06eHs6frl5A
-        let mut subject = neighborhood_from_nodes(&subject_node, Some(&next_door_neighbor));
-
+    fn neighborhood_graph_request_is_answered_with_nodes_edges_and_dot_graph() {
+        let (o, r, e, mut subject) = make_o_r_e_subject();
+        // make_node_record's keys are too short to be truncated
+        let long_keyed = NodeRecord::new_for_tests(
+            &PublicKey::new(b"a key too long to show whole"),
+            Some(&NodeAddr::new(
+                &IpAddr::from_str("9.8.7.6").unwrap(),
+                &[9876],
+            )),
+            100,
+            true,
+            true,
+        );
         subject
             .neighborhood_database
-            .add_node(next_door_neighbor.clone())
+            .add_node(long_keyed.clone())
             .unwrap();
         subject
             .neighborhood_database
-            .add_node(exit_node.clone())
-            .unwrap();
-
-        subject.neighborhood_database.add_arbitrary_full_neighbor(
-            subject_node.public_key(),
-            next_door_neighbor.public_key(),
+            .add_arbitrary_half_neighbor(e.public_key(), long_keyed.public_key());
+        let root_ip = o.node_addr_opt().unwrap().ip_addr().to_string();
+        let data_directory = ensure_node_home_directory_exists(
+            "neighborhood",
+            "neighborhood_graph_request_is_answered_with_nodes_edges_and_dot_graph",
         );
+        let database_path = data_directory.join(GEO_DB_FILENAME);
+        fs::write(&database_path, tiny_country_database()).unwrap();
+        let (geo_db, _) = GeoDbHandle::new_for_test(
+            "https://geo.example.com/geo-ip.mmdb",
+            GeoDbStatus {
+                updated_at_opt: Some(SystemTime::now()),
+                ..GeoDbStatus::default()
+            },
+        );
+        subject.geo_db_opt = Some(geo_db.with_database_path(database_path));
+        let system = System::new("test");
+        let subject_addr: Addr<Neighborhood> = subject.start();
+        let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
+        let peer_actors = peer_actors_builder().ui_gateway(ui_gateway).build();
+        subject_addr.try_send(BindMessage { peer_actors }).unwrap();
 
-        subject
-            .neighborhood_database
-            .add_arbitrary_full_neighbor(next_door_neighbor.public_key(), exit_node.public_key());
-
-        let minimum_hop_count = 2;
-
-        let result = subject.make_round_trip_route(RouteQueryMessage {
-            target_key_opt: None,
-            target_component: Component::ProxyClient,
-            minimum_hop_count,
-            return_component_opt: Some(Component::ProxyServer),
-        });
-
-        let next_door_neighbor_cryptde =
-            CryptDENull::from(&next_door_neighbor.public_key(), DEFAULT_CHAIN_ID);
-        let exit_node_cryptde = CryptDENull::from(&exit_node.public_key(), DEFAULT_CHAIN_ID);
+        subject_addr
+            .try_send(NodeFromUiMessage {
+                client_id: 1234,
+                body: UiNeighborhoodGraphRequest {}.tmb(4321),
+            })
+            .unwrap();
 
-        let hops = result.clone().unwrap().route.hops;
-        let actual_keys: Vec<PublicKey> = match hops.as_slice() {
-            [hop, exit, hop_back, origin, empty, _accounting] => vec![
-                decodex::<LiveHop>(main_cryptde(), hop)
-                    .expect("hop")
-                    .public_key,
-                decodex::<LiveHop>(&next_door_neighbor_cryptde, exit)
-                    .expect("exit")
-                    .public_key,
-                decodex::<LiveHop>(&exit_node_cryptde, hop_back)
-                    .expect("hop_back")
-                    .public_key,
-                decodex::<LiveHop>(&next_door_neighbor_cryptde, origin)
-                    .expect("origin")
-                    .public_key,
-                decodex::<LiveHop>(main_cryptde(), empty)
-                    .expect("empty")
-                    .public_key,
-            ],
-            l => panic!("our match is wrong, real size is {}, {:?}", l.len(), l),
+        System::current().stop();
+        system.run();
+        let ui_gateway_recording = ui_gateway_recording_arc.lock().unwrap();
+        let message = ui_gateway_recording.get_record::<NodeToUiMessage>(0);
+        assert_eq!(message.target, ClientId(1234));
+        let (response, context_id) =
+            UiNeighborhoodGraphResponse::fmb(message.body.clone()).unwrap();
+        assert_eq!(context_id, 4321);
+        assert_eq!(response.dot_graph.starts_with("digraph db {"), true);
+        assert_eq!(
+            response.dot_graph.contains(&format!(
+                "\"{}\" -> \"{}\"",
+                truncated_key(e.public_key()),
+                truncated_key(long_keyed.public_key())
+            )),
+            true
+        );
+        assert_eq!(
+            response
+                .dot_graph
+                .contains(&long_keyed.public_key().to_string()),
+            false
+        );
+        assert_eq!(response.dot_graph.contains("9.8.7.6"), false);
+        assert_eq!(response.dot_graph.contains(&root_ip), false);
+        let expected_node = |node: &NodeRecord, is_local: bool| UiNeighborhoodGraphNode {
+            public_key: truncated_key(node.public_key()),
+            is_local,
+            version: node.version(),
+            accepts_connections: node.accepts_connections(),
+            routes_data: node.routes_data(),
+            rate_pack: UiRatePack {
+                routing_byte_rate: node.rate_pack().routing_byte_rate,
+                routing_service_rate: node.rate_pack().routing_service_rate,
+                exit_byte_rate: node.rate_pack().exit_byte_rate,
+                exit_service_rate: node.rate_pack().exit_service_rate,
+            },
+            // The tiny database puts the first half of the IPv4 addresses in the US
+            country_code_opt: match node.node_addr_opt().map(|node_addr| node_addr.ip_addr()) {
+                Some(IpAddr::V4(ip)) if ip.octets()[0] < 128 => Some("US".to_string()),
+                _ => None,
+            },
         };
-        let expected_public_keys = vec![
-            next_door_neighbor.public_key().clone(),
-            exit_node.public_key().clone(),
-            next_door_neighbor.public_key().clone(),
-            subject_node.public_key().clone(),
-            PublicKey::new(b""),
+        let expected_nodes = vec![(&o, true), (&r, false), (&e, false), (&long_keyed, false)]
+            .into_iter()
+            .map(|(node, is_local)| expected_node(node, is_local))
+            .sorted_by(|a, b| a.public_key.cmp(&b.public_key))
+            .collect_vec();
+        let actual_nodes = response
+            .nodes
+            .into_iter()
+            .sorted_by(|a, b| a.public_key.cmp(&b.public_key))
+            .collect_vec();
+        assert_eq!(actual_nodes, expected_nodes);
+        let edge = |a: &NodeRecord, b: &NodeRecord| UiNeighborhoodGraphEdge {
+            from: truncated_key(a.public_key()),
+            to: truncated_key(b.public_key()),
+        };
+        let expected_edges = [
+            edge(&o, &r),
+            edge(&r, &o),
+            edge(&r, &e),
+            edge(&e, &r),
+            edge(&e, &long_keyed),
         ];
-        assert_eq!(expected_public_keys, actual_keys);
+        let actual_edges = response.edges;
+        assert_eq!(actual_edges.len(), expected_edges.len());
+        expected_edges
+            .iter()
+            .for_each(|expected| assert_contains(&actual_edges, expected));
     }
 
     #[test]
-    #[should_panic(
-        expected = "Neighborhood should never get ShutdownStreamMsg about non-clandestine stream"
-    )]
-    fn handle_stream_shutdown_complains_about_non_clandestine_message() {
-        let subject_node = make_global_cryptde_node_record(1345, true);
-        let mut subject = neighborhood_from_nodes(&subject_node, None);
+    fn route_quote_request_is_answered_with_the_cost_of_each_available_route() {
+        let (_, r, e, subject) = make_o_r_e_subject();
+        let system = System::new("test");
+        let subject_addr: Addr<Neighborhood> = subject.start();
+        let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
+        let peer_actors = peer_actors_builder().ui_gateway(ui_gateway).build();
+        subject_addr.try_send(BindMessage { peer_actors }).unwrap();
 
-        subject.handle_stream_shutdown_msg(StreamShutdownMsg {
-            peer_addr: SocketAddr::from_str("1.2.3.4:5678").unwrap(),
-            stream_type: RemovedStreamType::NonClandestine(NonClandestineAttributes {
-                reception_port: TLS_PORT,
-                sequence_number: 1234,
-            }),
-            report_to_counterpart: false,
-        });
+        subject_addr
+            .try_send(NodeFromUiMessage {
+                client_id: 1234,
+                body: UiRouteQuoteRequest {
+                    hop_counts_opt: Some(vec![2, 3]),
+                }
+                .tmb(4321),
+            })
+            .unwrap();
+
+        System::current().stop();
+        system.run();
+        let ui_gateway_recording = ui_gateway_recording_arc.lock().unwrap();
+        let message = ui_gateway_recording.get_record::<NodeToUiMessage>(0);
+        assert_eq!(message.target, ClientId(1234));
+        let (response, context_id) = UiRouteQuoteResponse::fmb(message.body.clone()).unwrap();
+        assert_eq!(context_id, 4321);
+        let (r_rates, e_rates) = (r.rate_pack(), e.rate_pack());
+        assert_eq!(
+            response,
+            UiRouteQuoteResponse {
+                quotes: vec![UiRouteQuote {
+                    hop_count: 2,
+                    exit_key: truncated_key(e.public_key()),
+                    request_cost_per_mb: (r_rates.routing_byte_rate + e_rates.exit_byte_rate)
+                        * BYTES_PER_MB,
+                    response_cost_per_mb: (e_rates.exit_byte_rate + r_rates.routing_byte_rate)
+                        * BYTES_PER_MB,
+                    service_cost: 2 * (r_rates.routing_service_rate + e_rates.exit_service_rate),
+                }],
+                unavailable_hop_counts: vec![3],
+            }
+        );
     }
 
     #[test]
-    fn handle_stream_shutdown_handles_socket_addr_with_unknown_ip() {
-        init_test_logging();
-        let (hopper, _, hopper_recording_arc) = make_recorder();
+    fn route_quote_request_for_too_many_hops_is_refused() {
+        let (_, _, _, subject) = make_o_r_e_subject();
         let system = System::new("test");
-        let unrecognized_node = make_node_record(3123, true);
-        let unrecognized_node_addr = unrecognized_node.node_addr_opt().unwrap();
-        let unrecognized_socket_addr = SocketAddr::new(
-            unrecognized_node_addr.ip_addr(),
-            unrecognized_node_addr.ports()[0],
-        );
-        let subject_node = make_global_cryptde_node_record(1345, true);
-        let mut subject = neighborhood_from_nodes(&subject_node, None);
-        let peer_actors = peer_actors_builder().hopper(hopper).build();
-        subject.hopper = Some(peer_actors.hopper.from_hopper_client);
+        let subject_addr: Addr<Neighborhood> = subject.start();
+        let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
+        let peer_actors = peer_actors_builder().ui_gateway(ui_gateway).build();
+        subject_addr.try_send(BindMessage { peer_actors }).unwrap();
 
-        subject.handle_stream_shutdown_msg(StreamShutdownMsg {
-            peer_addr: unrecognized_socket_addr,
-            stream_type: RemovedStreamType::Clandestine,
-            report_to_counterpart: true,
-        });
+        subject_addr
+            .try_send(NodeFromUiMessage {
+                client_id: 1234,
+                body: UiRouteQuoteRequest {
+                    hop_counts_opt: Some(vec![2, MAX_QUOTED_HOP_COUNT + 1]),
+                }
+                .tmb(4321),
+            })
+            .unwrap();
 
-        System::current().stop_with_code(0);
+        System::current().stop();
         system.run();
-
-        assert_eq!(subject.neighborhood_database.keys().len(), 1);
-        let hopper_recording = hopper_recording_arc.lock().unwrap();
-        assert_eq!(hopper_recording.len(), 0);
-        TestLogHandler::new().exists_log_containing(&format!("WARN: Neighborhood: Received shutdown notification for stream to {}, but no Node with that IP is in the database - ignoring", unrecognized_socket_addr.ip()));
+        let ui_gateway_recording = ui_gateway_recording_arc.lock().unwrap();
+        let message = ui_gateway_recording.get_record::<NodeToUiMessage>(0);
+        assert_eq!(
+            message.body,
+            MessageBody {
+                opcode: "routeQuote".to_string(),
+                path: Conversation(4321),
+                payload: Err((
+                    ROUTE_QUOTE_ERROR,
+                    format!(
+                        "Can't quote a {}-hop route: no more than {} hops, please",
+                        MAX_QUOTED_HOP_COUNT + 1,
+                        MAX_QUOTED_HOP_COUNT
+                    )
+                )),
+            }
+        );
     }
 
     #[test]
-    fn handle_stream_shutdown_handles_already_inactive_node() {
-        init_test_logging();
-        let (hopper, _, hopper_recording_arc) = make_recorder();
-        let system = System::new("test");
-        let gossip_neighbor_node = make_node_record(2456, true);
-        let inactive_neighbor_node = make_node_record(3123, true);
-        let inactive_neighbor_node_addr = inactive_neighbor_node.node_addr_opt().unwrap();
-        let inactive_neighbor_node_socket_addr = SocketAddr::new(
-            inactive_neighbor_node_addr.ip_addr(),
-            inactive_neighbor_node_addr.ports()[0],
+    fn audited_connection_from_a_known_node_is_recorded_under_its_key_and_served_to_the_ui() {
+        let home_dir = ensure_node_home_directory_exists(
+            "neighborhood",
+            "audited_connection_from_a_known_node_is_recorded_under_its_key_and_served_to_the_ui",
         );
-        let subject_node = make_global_cryptde_node_record(1345, true);
-        let mut subject = neighborhood_from_nodes(&subject_node, None);
+        let mut subject = make_standard_subject();
+        let neighbor = make_node_record(9998, true);
         subject
             .neighborhood_database
-            .add_node(gossip_neighbor_node.clone())
+            .add_node(neighbor.clone())
             .unwrap();
-        subject
-            .neighborhood_database
-            .add_node(inactive_neighbor_node.clone())
+        subject.connection_audit_dao_opt = Some(Box::new(ConnectionAuditDaoReal::new(
+            DbInitializerReal::new()
+                .initialize(&home_dir, DEFAULT_CHAIN_ID, true)
+                .unwrap(),
+        )));
+        let peer_addr = SocketAddr::new(neighbor.node_addr_opt().unwrap().ip_addr(), 5678);
+        let system = System::new("test");
+        let subject_addr: Addr<Neighborhood> = subject.start();
+        let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
+        let peer_actors = peer_actors_builder().ui_gateway(ui_gateway).build();
+        subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+
+        subject_addr
+            .try_send(ConnectionAuditMessage {
+                record: ConnectionAuditRecord {
+                    public_key_opt: None,
+                    peer_addr,
+                    direction: ConnectionDirection::Inbound,
+                    opened_at: 1_600_000_000,
+                    duration_ms: 12345,
+                    bytes_in: 1234,
+                    bytes_out: 4321,
+                    disconnect_reason: "closed by peer".to_string(),
+                },
+            })
             .unwrap();
-        subject.neighborhood_database.add_arbitrary_full_neighbor(
-            subject_node.public_key(),
-            gossip_neighbor_node.public_key(),
-        );
-        subject.neighborhood_database.add_arbitrary_half_neighbor(
-            inactive_neighbor_node.public_key(),
-            subject_node.public_key(),
+        subject_addr
+            .try_send(NodeFromUiMessage {
+                client_id: 1234,
+                body: UiConnectionAuditRequest {
+                    from_timestamp_opt: Some(1_600_000_000),
+                    to_timestamp_opt: None,
+                    limit_opt: None,
+                }
+                .tmb(4321),
+            })
+            .unwrap();
+
+        System::current().stop();
+        system.run();
+        let ui_gateway_recording = ui_gateway_recording_arc.lock().unwrap();
+        let message = ui_gateway_recording.get_record::<NodeToUiMessage>(0);
+        assert_eq!(message.target, ClientId(1234));
+        let (response, context_id) = UiConnectionAuditResponse::fmb(message.body.clone()).unwrap();
+        assert_eq!(context_id, 4321);
+        assert_eq!(
+            response,
+            UiConnectionAuditResponse {
+                connections: vec![UiAuditedConnection {
+                    public_key_opt: Some(neighbor.public_key().to_string()),
+                    peer_addr: peer_addr.to_string(),
+                    direction: "inbound".to_string(),
+                    opened_at: 1_600_000_000,
+                    duration_ms: 12345,
+                    bytes_in: 1234,
+                    bytes_out: 4321,
+                    disconnect_reason: "closed by peer".to_string(),
+                }],
+            }
         );
-        let peer_actors = peer_actors_builder().hopper(hopper).build();
-        subject.hopper = Some(peer_actors.hopper.from_hopper_client);
+    }
 
-        subject.handle_stream_shutdown_msg(StreamShutdownMsg {
-            peer_addr: inactive_neighbor_node_socket_addr,
-            stream_type: RemovedStreamType::Clandestine,
-            report_to_counterpart: true,
-        });
+    #[test]
+    fn connection_audit_request_with_a_backward_period_is_refused() {
+        let subject = make_standard_subject();
+        let system = System::new("test");
+        let subject_addr: Addr<Neighborhood> = subject.start();
+        let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
+        let peer_actors = peer_actors_builder().ui_gateway(ui_gateway).build();
+        subject_addr.try_send(BindMessage { peer_actors }).unwrap();
 
-        System::current().stop_with_code(0);
-        system.run();
+        subject_addr
+            .try_send(NodeFromUiMessage {
+                client_id: 1234,
+                body: UiConnectionAuditRequest {
+                    from_timestamp_opt: Some(2000),
+                    to_timestamp_opt: Some(1999),
+                    limit_opt: None,
+                }
+                .tmb(4321),
+            })
+            .unwrap();
 
-        assert_eq!(subject.neighborhood_database.keys().len(), 3);
+        System::current().stop();
+        system.run();
+        let ui_gateway_recording = ui_gateway_recording_arc.lock().unwrap();
+        let message = ui_gateway_recording.get_record::<NodeToUiMessage>(0);
         assert_eq!(
-            subject.neighborhood_database.has_half_neighbor(
-                subject_node.public_key(),
-                inactive_neighbor_node.public_key(),
-            ),
-            false
+            message.body,
+            MessageBody {
+                opcode: "connectionAudit".to_string(),
+                path: Conversation(4321),
+                payload: Err((
+                    CONNECTION_AUDIT_ERROR,
+                    "fromTimestamp 2000 is later than toTimestamp 1999".to_string()
+                )),
+            }
         );
-        let hopper_recording = hopper_recording_arc.lock().unwrap();
-        assert_eq!(hopper_recording.len(), 0);
-        TestLogHandler::new().exists_log_containing(&format!("DEBUG: Neighborhood: Received shutdown notification for {} at {}, but that Node is no neighbor - ignoring", inactive_neighbor_node.public_key(), inactive_neighbor_node_socket_addr.ip()));
     }
 
     #[test]
-    fn handle_stream_shutdown_handles_existing_socket_addr() {
-        init_test_logging();
-        let (hopper, _, hopper_recording_arc) = make_recorder();
+    fn connection_audit_request_before_the_database_is_open_is_refused() {
+        let subject = make_standard_subject();
         let system = System::new("test");
-        let gossip_neighbor_node = make_node_record(2456, true);
-        let shutdown_neighbor_node = make_node_record(3123, true);
-        let shutdown_neighbor_node_addr = shutdown_neighbor_node.node_addr_opt().unwrap();
-        let shutdown_neighbor_node_socket_addr = SocketAddr::new(
-            shutdown_neighbor_node_addr.ip_addr(),
-            shutdown_neighbor_node_addr.ports()[0],
-        );
-        let subject_node = make_global_cryptde_node_record(1345, true);
-        let mut subject = neighborhood_from_nodes(&subject_node, None);
-        subject
-            .neighborhood_database
-            .add_node(gossip_neighbor_node.clone())
-            .unwrap();
-        subject
-            .neighborhood_database
-            .add_node(shutdown_neighbor_node.clone())
+        let subject_addr: Addr<Neighborhood> = subject.start();
+        let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
+        let peer_actors = peer_actors_builder().ui_gateway(ui_gateway).build();
+        subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+
+        subject_addr
+            .try_send(NodeFromUiMessage {
+                client_id: 1234,
+                body: UiConnectionAuditRequest {
+                    from_timestamp_opt: None,
+                    to_timestamp_opt: None,
+                    limit_opt: Some(10),
+                }
+                .tmb(4321),
+            })
             .unwrap();
-        subject.neighborhood_database.add_arbitrary_full_neighbor(
-            subject_node.public_key(),
-            gossip_neighbor_node.public_key(),
+
+        System::current().stop();
+        system.run();
+        let ui_gateway_recording = ui_gateway_recording_arc.lock().unwrap();
+        let message = ui_gateway_recording.get_record::<NodeToUiMessage>(0);
+        assert_eq!(
+            message.body,
+            MessageBody {
+                opcode: "connectionAudit".to_string(),
+                path: Conversation(4321),
+                payload: Err((
+                    CONNECTION_AUDIT_ERROR,
+                    "The connection audit log isn't open yet".to_string()
+                )),
+            }
         );
-        subject.neighborhood_database.add_arbitrary_full_neighbor(
-            subject_node.public_key(),
-            shutdown_neighbor_node.public_key(),
+    }
+
+    #[test]
+    fn geo_database_request_reports_how_the_database_is_doing() {
+        let mut subject = make_standard_subject();
+        let (geo_db, _) = GeoDbHandle::new_for_test(
+            "https://geo.example.com/geo-ip.mmdb",
+            GeoDbStatus {
+                updated_at_opt: Some(SystemTime::now() - Duration::from_secs(7200)),
+                checked_at_opt: Some(UNIX_EPOCH + Duration::from_secs(1_600_000_000)),
+                error_opt: Some("Connection refused".to_string()),
+                refreshing: false,
+            },
         );
-        let peer_actors = peer_actors_builder().hopper(hopper).build();
-        subject.hopper = Some(peer_actors.hopper.from_hopper_client);
+        subject.geo_db_opt = Some(geo_db);
+        let system = System::new("test");
+        let subject_addr: Addr<Neighborhood> = subject.start();
+        let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
+        let peer_actors = peer_actors_builder().ui_gateway(ui_gateway).build();
+        subject_addr.try_send(BindMessage { peer_actors }).unwrap();
 
-        subject.handle_stream_shutdown_msg(StreamShutdownMsg {
-            peer_addr: shutdown_neighbor_node_socket_addr,
-            stream_type: RemovedStreamType::Clandestine,
-            report_to_counterpart: true,
-        });
+        subject_addr
+            .try_send(NodeFromUiMessage {
+                client_id: 1234,
+                body: UiGeoDatabaseRequest {}.tmb(4321),
+            })
+            .unwrap();
 
-        System::current().stop_with_code(0);
+        System::current().stop();
         system.run();
-
-        assert_eq!(subject.neighborhood_database.keys().len(), 3);
+        let ui_gateway_recording = ui_gateway_recording_arc.lock().unwrap();
+        let message = ui_gateway_recording.get_record::<NodeToUiMessage>(0);
+        assert_eq!(message.target, ClientId(1234));
+        let (response, context_id) = UiGeoDatabaseResponse::fmb(message.body.clone()).unwrap();
+        assert_eq!(context_id, 4321);
+        let age_seconds = response.age_seconds_opt.unwrap();
+        assert!((7200..7260).contains(&age_seconds), "{}", age_seconds);
         assert_eq!(
-            subject.neighborhood_database.has_half_neighbor(
-                subject_node.public_key(),
-                shutdown_neighbor_node.public_key(),
-            ),
-            false
+            response,
+            UiGeoDatabaseResponse {
+                mirror_url: "https://geo.example.com/geo-ip.mmdb".to_string(),
+                age_seconds_opt: Some(age_seconds),
+                last_checked_opt: Some(1_600_000_000),
+                last_error_opt: Some("Connection refused".to_string()),
+                refreshing: false,
+            }
         );
-        let hopper_recording = hopper_recording_arc.lock().unwrap();
-        assert_eq!(hopper_recording.len(), 1);
-        TestLogHandler::new().exists_log_containing(&format!(
-            "DEBUG: Neighborhood: Received shutdown notification for {} at {}: removing neighborship",
-            shutdown_neighbor_node.public_key(),
-            shutdown_neighbor_node_socket_addr.ip()
-        ));
     }
 
-    #[should_panic(expected = "0: Received shutdown order from client 1234: shutting down hard")]
     #[test]
-    fn shutdown_instruction_generates_log() {
-        running_test();
-        init_test_logging();
-        let system = System::new("test");
-        let subject = Neighborhood::new(
-            main_cryptde(),
-            &bc_from_nc_plus(
-                NeighborhoodConfig {
-                    mode: NeighborhoodMode::ZeroHop,
-                },
-                make_wallet("earning"),
-                None,
-                "unexpected_ui_message_is_logged_and_ignored",
-            ),
+    fn geo_database_refresh_request_wakes_the_updater() {
+        let mut subject = make_standard_subject();
+        let (geo_db, refresh_rx) = GeoDbHandle::new_for_test(
+            "https://geo.example.com/geo-ip.mmdb",
+            GeoDbStatus::default(),
         );
+        subject.geo_db_opt = Some(geo_db);
+        let system = System::new("test");
+        let subject_addr: Addr<Neighborhood> = subject.start();
         let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
-        let subject_addr = subject.start();
         let peer_actors = peer_actors_builder().ui_gateway(ui_gateway).build();
         subject_addr.try_send(BindMessage { peer_actors }).unwrap();
 
         subject_addr
             .try_send(NodeFromUiMessage {
                 client_id: 1234,
-                body: MessageBody {
-                    opcode: "shutdown".to_string(),
-                    path: Conversation(4321),
-                    payload: Ok("{}".to_string()),
-                },
+                body: UiGeoDatabaseRefreshRequest {}.tmb(4321),
             })
             .unwrap();
 
         System::current().stop();
         system.run();
+        assert_eq!(refresh_rx.try_recv(), Ok(()));
         let ui_gateway_recording = ui_gateway_recording_arc.lock().unwrap();
-        assert_eq!(ui_gateway_recording.len(), 0);
-        TestLogHandler::new()
-            .exists_log_containing("INFO: Neighborhood: Received shutdown order from client 1234");
+        let message = ui_gateway_recording.get_record::<NodeToUiMessage>(0);
+        assert_eq!(
+            UiGeoDatabaseRefreshResponse::fmb(message.body.clone()).unwrap(),
+            (UiGeoDatabaseRefreshResponse {}, 4321)
+        );
     }
 
     #[test]
-    fn unexpected_ui_message_is_logged_and_ignored() {
-        init_test_logging();
-        let subject = Neighborhood::new(
-            main_cryptde(),
-            &bc_from_nc_plus(
-                NeighborhoodConfig {
-                    mode: NeighborhoodMode::ZeroHop,
-                },
-                make_wallet("earning"),
-                None,
-                "unexpected_ui_message_is_logged_and_ignored",
-            ),
-        );
+    fn geo_database_requests_are_refused_without_a_mirror() {
+        let subject = make_standard_subject();
         let system = System::new("test");
         let subject_addr: Addr<Neighborhood> = subject.start();
         let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
@@ -4205,20 +8770,289 @@ mod tests {
         subject_addr
             .try_send(NodeFromUiMessage {
                 client_id: 1234,
-                body: MessageBody {
-                    opcode: "booga".to_string(),
-                    path: FireAndForget,
-                    payload: Ok("{}".to_string()),
-                },
+                body: UiGeoDatabaseRequest {}.tmb(4321),
+            })
+            .unwrap();
+        subject_addr
+            .try_send(NodeFromUiMessage {
+                client_id: 1234,
+                body: UiGeoDatabaseRefreshRequest {}.tmb(4322),
             })
             .unwrap();
 
         System::current().stop();
         system.run();
         let ui_gateway_recording = ui_gateway_recording_arc.lock().unwrap();
-        assert_eq!(ui_gateway_recording.len(), 0);
-        TestLogHandler::new().exists_log_containing(
-            "DEBUG: Neighborhood: Ignoring 'booga' request from client 1234",
+        let error = |opcode: &str, context_id: u64| MessageBody {
+            opcode: opcode.to_string(),
+            path: Conversation(context_id),
+            payload: Err((
+                GEO_DATABASE_ERROR,
+                "This Node keeps no geolocation database: start it with --geo-db-url".to_string(),
+            )),
+        };
+        assert_eq!(
+            ui_gateway_recording.get_record::<NodeToUiMessage>(0).body,
+            error("geoDatabase", 4321)
+        );
+        assert_eq!(
+            ui_gateway_recording.get_record::<NodeToUiMessage>(1).body,
+            error("geoDatabaseRefresh", 4322)
+        );
+    }
+
+    #[test]
+    fn collect_diagnostics_request_is_answered_with_a_bundle_holding_a_neighborhood_snapshot() {
+        let data_directory = ensure_node_home_directory_exists(
+            "neighborhood",
+            "collect_diagnostics_request_is_answered_with_a_bundle_holding_a_neighborhood_snapshot",
+        );
+        let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
+        let (tx, rx) = std::sync::mpsc::channel();
+        let subject_data_directory = data_directory.clone();
+        // The Neighborhood isn't Send, so it's made on the thread it runs on
+        thread::spawn(move || {
+            let (_, r, e, mut subject) = make_o_r_e_subject();
+            subject.data_directory = subject_data_directory;
+            subject.timing_cryptde_opt = Some(Box::new(CryptDENull::new(DEFAULT_CHAIN_ID)));
+            // make_node_record's keys are too short to be truncated
+            let long_keyed = NodeRecord::new_for_tests(
+                &PublicKey::new(b"a key too long to show whole"),
+                None,
+                100,
+                true,
+                true,
+            );
+            subject
+                .neighborhood_database
+                .add_node(long_keyed.clone())
+                .unwrap();
+            subject.route_latencies.push_back(vec![
+                (e.public_key().clone(), 30),
+                (r.public_key().clone(), 12),
+            ]);
+            let system = System::new("test");
+            let subject_addr: Addr<Neighborhood> = subject.start();
+            let peer_actors = peer_actors_builder().ui_gateway(ui_gateway).build();
+            subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+            tx.send((subject_addr, r, e, long_keyed)).unwrap();
+            system.run();
+        });
+        let (subject_addr, r, e, long_keyed) = rx.recv().unwrap();
+
+        subject_addr
+            .try_send(NodeFromUiMessage {
+                client_id: 1234,
+                body: UiCollectDiagnosticsRequest {}.tmb(4321),
+            })
+            .unwrap();
+
+        wait_for(None, None, || {
+            !ui_gateway_recording_arc.lock().unwrap().is_empty()
+        });
+        let ui_gateway_recording = ui_gateway_recording_arc.lock().unwrap();
+        let message = ui_gateway_recording.get_record::<NodeToUiMessage>(0);
+        assert_eq!(message.target, ClientId(1234));
+        let (response, context_id) =
+            UiCollectDiagnosticsResponse::fmb(message.body.clone()).unwrap();
+        assert_eq!(context_id, 4321);
+        let bundle_path = PathBuf::from(response.path);
+        assert_eq!(bundle_path.parent().unwrap(), data_directory.as_path());
+        let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(
+            std::fs::File::open(bundle_path).unwrap(),
+        ));
+        let mut snapshot = String::new();
+        let mut metrics_text = String::new();
+        let mut route_latency_text = String::new();
+        archive
+            .entries()
+            .unwrap()
+            .map(|entry| entry.unwrap())
+            .for_each(|mut entry| {
+                let path = entry.path().unwrap().to_string_lossy().to_string();
+                match path.as_str() {
+                    "neighborhood.json" => entry.read_to_string(&mut snapshot).unwrap(),
+                    "metrics.txt" => entry.read_to_string(&mut metrics_text).unwrap(),
+                    "route_latency.txt" => entry.read_to_string(&mut route_latency_text).unwrap(),
+                    _ => 0,
+                };
+            });
+        assert_eq!(
+            snapshot.contains(&truncated_key(long_keyed.public_key())),
+            true
+        );
+        assert_eq!(
+            snapshot.contains(&long_keyed.public_key().to_string()),
+            false
+        );
+        assert_eq!(metrics_text.contains("masq_replayed_packages_total"), true);
+        assert_eq!(
+            route_latency_text,
+            format!(
+                "{} 30ms, {} 12ms, total 42ms\n",
+                truncated_key(e.public_key()),
+                truncated_key(r.public_key())
+            )
+        );
+    }
+
+    #[test]
+    fn hop_timing_gives_the_neighborhood_a_timing_key_of_its_own() {
+        let cryptde = main_cryptde();
+        let mut config = bc_from_nc_plus(
+            NeighborhoodConfig {
+                mode: NeighborhoodMode::ZeroHop,
+            },
+            make_wallet("earning"),
+            None,
+            "hop_timing_gives_the_neighborhood_a_timing_key_of_its_own",
+        );
+        let without = Neighborhood::new(cryptde, &config);
+        config.hop_timing = true;
+
+        let with = Neighborhood::new(cryptde, &config);
+
+        assert_eq!(without.timing_cryptde_opt.is_none(), true);
+        let timing_key = with.timing_cryptde_opt.unwrap().public_key().clone();
+        assert_ne!(&timing_key, cryptde.public_key());
+        assert_ne!(&timing_key, alias_cryptde().public_key());
+    }
+
+    #[test]
+    fn gossip_recording_writes_received_gossip_into_the_data_directory_under_pseudonyms() {
+        let data_directory = ensure_node_home_directory_exists(
+            "neighborhood",
+            "gossip_recording_writes_received_gossip_into_the_data_directory_under_pseudonyms",
+        );
+        let mut subject = make_standard_subject();
+        subject.data_directory = data_directory.clone();
+        subject.gossip_acceptor =
+            Box::new(GossipAcceptorMock::new().handle_result(GossipAcceptanceResult::Ignored));
+        let debutant = make_node_record(2345, true);
+        let gossip = GossipBuilder::new(&db_from_node(&debutant))
+            .node(debutant.public_key(), true)
+            .build();
+        subject.start_gossip_recording();
+
+        subject.handle_gossip(gossip, SocketAddr::from_str("2.3.4.5:2345").unwrap());
+
+        let mut file = File::open(data_directory.join(GOSSIP_RECORDING_FILE)).unwrap();
+        let entries = read_recording(&mut file).unwrap();
+        match &entries[..] {
+            [RecordingEntry::Start { local, .. }, RecordingEntry::Gossip { records, .. }] => {
+                assert_eq!(local.public_key, PublicKey::new(b"Node0000"));
+                assert_eq!(records[0].0.public_key, PublicKey::new(b"Node0001"));
+                assert_eq!(records.len(), 1);
+            }
+            x => panic!("Expected Start and Gossip, got {:?}", x),
+        }
+    }
+
+    #[test]
+    fn dump_neighborhood_rewrites_the_graph_files_when_the_database_changes() {
+        let data_directory = ensure_node_home_directory_exists(
+            "neighborhood",
+            "dump_neighborhood_rewrites_the_graph_files_when_the_database_changes",
+        );
+        let (o, r, _, mut subject) = make_o_r_e_subject();
+        subject.data_directory = data_directory.clone();
+        subject.dump_neighborhood = true;
+        let neighbor_keys = vec![r.public_key().clone()];
+
+        subject.handle_database_changes(&neighbor_keys, &neighbor_keys);
+
+        let json = fs::read_to_string(data_directory.join(NEIGHBORHOOD_JSON_FILE)).unwrap();
+        let dot = fs::read_to_string(data_directory.join(NEIGHBORHOOD_DOT_FILE)).unwrap();
+        let graph = subject.neighborhood_graph();
+        assert_eq!(json, graph_snapshot(&graph));
+        assert_eq!(dot, graph.dot_graph);
+        assert_eq!(graph.nodes.len(), 3);
+        let root_ip = o.node_addr_opt().unwrap().ip_addr().to_string();
+        assert_eq!(json.contains(&root_ip), false);
+        assert_eq!(dot.contains(&root_ip), false);
+    }
+
+    #[test]
+    fn neighborhood_is_not_dumped_unless_asked() {
+        let data_directory = ensure_node_home_directory_exists(
+            "neighborhood",
+            "neighborhood_is_not_dumped_unless_asked",
+        );
+        let (_, r, _, mut subject) = make_o_r_e_subject();
+        subject.data_directory = data_directory.clone();
+        let neighbor_keys = vec![r.public_key().clone()];
+
+        subject.handle_database_changes(&neighbor_keys, &neighbor_keys);
+
+        assert_eq!(data_directory.join(NEIGHBORHOOD_JSON_FILE).exists(), false);
+        assert_eq!(data_directory.join(NEIGHBORHOOD_DOT_FILE).exists(), false);
+    }
+
+    #[test]
+    fn hop_timings_are_turned_into_how_long_each_node_held_the_response() {
+        init_test_logging();
+        let mut subject = make_standard_subject();
+        let timing_cryptde = CryptDENull::new(DEFAULT_CHAIN_ID);
+        let stamp = |public_key: &PublicKey, sent_at_ms: u64| {
+            encodex(
+                main_cryptde(),
+                timing_cryptde.public_key(),
+                &HopTiming {
+                    public_key: public_key.clone(),
+                    sent_at_ms,
+                },
+            )
+            .unwrap()
+        };
+        let exit_key = PublicKey::new(b"exit node");
+        let relay_key = PublicKey::new(b"relay node");
+        let timings = vec![stamp(&exit_key, 1_000), stamp(&relay_key, 1_012)];
+        subject.timing_cryptde_opt = Some(Box::new(timing_cryptde.clone()));
+
+        subject.handle_hop_timings(HopTimingsMessage {
+            timings,
+            received_at_ms: 1_020,
+        });
+
+        assert_eq!(
+            subject.route_latencies,
+            VecDeque::from(vec![vec![(exit_key.clone(), 12), (relay_key.clone(), 8)]])
+        );
+        TestLogHandler::new().exists_log_containing(&format!(
+            "DEBUG: Neighborhood: Route back: {} 12ms, {} 8ms, total 20ms",
+            truncated_key(&exit_key),
+            truncated_key(&relay_key)
+        ));
+    }
+
+    #[test]
+    fn hop_timings_keep_only_the_most_recent_routes_and_are_ignored_when_hop_timing_is_off() {
+        let mut subject = make_standard_subject();
+        let timing_cryptde = CryptDENull::new(DEFAULT_CHAIN_ID);
+        let message = |sent_at_ms: u64| HopTimingsMessage {
+            timings: vec![encodex(
+                main_cryptde(),
+                timing_cryptde.public_key(),
+                &HopTiming {
+                    public_key: PublicKey::new(b"exit node"),
+                    sent_at_ms,
+                },
+            )
+            .unwrap()],
+            received_at_ms: 2_000,
+        };
+        subject.handle_hop_timings(message(1_000));
+        assert_eq!(subject.route_latencies.is_empty(), true);
+        subject.timing_cryptde_opt = Some(Box::new(timing_cryptde.clone()));
+
+        (0..=MAX_ROUTE_LATENCIES as u64)
+            .for_each(|millis| subject.handle_hop_timings(message(2_000 - millis)));
+
+        assert_eq!(subject.route_latencies.len(), MAX_ROUTE_LATENCIES);
+        assert_eq!(subject.route_latencies.front().unwrap()[0].1, 1);
+        assert_eq!(
+            subject.route_latencies.back().unwrap()[0].1,
+            MAX_ROUTE_LATENCIES as i64
         );
     }
 
@@ -4267,6 +9101,7 @@ mod tests {
             >,
         >,
         handle_results: RefCell<Vec<GossipAcceptanceResult>>,
+        apply_records: bool,
     }
 
     impl GossipAcceptor for GossipAcceptorMock {
@@ -4276,6 +9111,13 @@ mod tests {
             agrs: Vec<AccessibleGossipRecord>,
             gossip_source: SocketAddr,
         ) -> GossipAcceptanceResult {
+            if self.apply_records {
+                agrs.iter().for_each(|agr| {
+                    if let Some(node) = database.node_by_key_mut(&agr.inner.public_key) {
+                        *node = NodeRecord::from(agr);
+                    }
+                });
+            }
             self.handle_params
                 .lock()
                 .unwrap()
@@ -4289,9 +9131,16 @@ mod tests {
             GossipAcceptorMock {
                 handle_params: Arc::new(Mutex::new(vec![])),
                 handle_results: RefCell::new(vec![]),
+                apply_records: false,
             }
         }
 
+        // Lets records of Nodes already in the database replace them, as a real acceptor might
+        pub fn apply_records(mut self) -> GossipAcceptorMock {
+            self.apply_records = true;
+            self
+        }
+
         pub fn handle_params(
             mut self,
             params_arc: &Arc<
@@ -4,11 +4,14 @@ use crate::neighborhood::dot_graph::{
     render_dot_graph, DotRenderable, EdgeRenderable, NodeRenderable, NodeRenderableInner,
 };
 use crate::neighborhood::node_record::{NodeRecord, NodeRecordError};
+use crate::sub_lib::cipher_suite;
 use crate::sub_lib::cryptde::CryptDE;
 use crate::sub_lib::cryptde::PublicKey;
+use crate::sub_lib::hybrid_kem;
 use crate::sub_lib::logger::Logger;
 use crate::sub_lib::neighborhood::NeighborhoodMode;
 use crate::sub_lib::node_addr::NodeAddr;
+use crate::sub_lib::path_mtu;
 use crate::sub_lib::utils::time_t_timestamp;
 use crate::sub_lib::wallet::Wallet;
 use itertools::Itertools;
@@ -18,14 +21,35 @@ use std::fmt::Debug;
 use std::fmt::Error;
 use std::fmt::Formatter;
 use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
 
 pub const ISOLATED_NODE_GRACE_PERIOD_SECS: u32 = 30;
+// A record that hasn't had a new version in this long is taken to describe a Node that's gone
+pub const NODE_RECORD_TTL_SECS: u32 = 30 * 60;
+// Live Nodes publish a new version of their records this often, so they never get that old
+pub const NODE_RECORD_REFRESH_INTERVAL_SECS: u32 = NODE_RECORD_TTL_SECS / 3;
+
+// Shared by every database, so that no two versions of any two databases have the same generation
+static NEXT_GENERATION: AtomicU64 = AtomicU64::new(1);
+
+// What's left of a Node that has departed or expired: stale Gossip about it, at or below the
+// version it was buried at, mustn't bring it back. A Node that left of its own accord also leaves
+// the signed record saying so, to be passed along to Nodes that haven't heard.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tombstone {
+    pub version: u32,
+    pub buried_at: u32,
+    pub record_opt: Option<NodeRecord>,
+}
 
 #[derive(Clone)]
 pub struct NeighborhoodDatabase {
     this_node: PublicKey,
     by_public_key: HashMap<PublicKey, NodeRecord>,
     by_ip_addr: HashMap<IpAddr, PublicKey>,
+    tombstones: HashMap<PublicKey, Tombstone>,
+    // Changes whenever a node record may have, so that what's derived from them can be kept
+    generation: u64,
     logger: Logger,
 }
 
@@ -46,6 +70,8 @@ impl NeighborhoodDatabase {
             this_node: public_key.clone(),
             by_public_key: HashMap::new(),
             by_ip_addr: HashMap::new(),
+            tombstones: HashMap::new(),
+            generation: 0,
             logger: Logger::new("NeighborhoodDatabase"),
         };
 
@@ -86,9 +112,14 @@ impl NeighborhoodDatabase {
     }
 
     pub fn node_by_key_mut(&mut self, public_key: &PublicKey) -> Option<&mut NodeRecord> {
+        self.next_generation();
         self.by_public_key.get_mut(public_key)
     }
 
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
     pub fn node_by_ip(&self, ip_addr: &IpAddr) -> Option<&NodeRecord> {
         match self.by_ip_addr.get(ip_addr) {
             Some(key) => self.node_by_key(key),
@@ -201,9 +232,17 @@ impl NeighborhoodDatabase {
             None => None,
             Some(node) => node.node_addr_opt().map(|na| na.ip_addr()),
         };
-        let _ = self.by_public_key.remove(node_key).is_some();
+        self.next_generation();
+        if self.by_public_key.remove(node_key).is_some() {
+            cipher_suite::forget_peer_features(node_key);
+            hybrid_kem::forget_peer_kem_key(node_key);
+        }
         if let Some(ip_addr) = ip_addr_opt {
-            let _ = self.by_ip_addr.remove(&ip_addr).is_some();
+            // After a key rotation, the IP address may already belong to the Node's new key
+            if self.by_ip_addr.get(&ip_addr) == Some(node_key) {
+                let _ = self.by_ip_addr.remove(&ip_addr).is_some();
+                path_mtu::forget_path_mtu(&ip_addr);
+            }
         }
     }
 
@@ -234,6 +273,74 @@ impl NeighborhoodDatabase {
         }
     }
 
+    // Removes the Node, and keeps Gossip no newer than version from bringing it back
+    pub fn bury(&mut self, node_key: &PublicKey, version: u32, record_opt: Option<NodeRecord>) {
+        if node_key == &self.this_node {
+            return;
+        }
+        self.remove_node(node_key);
+        if self.root_mut().remove_half_neighbor_key(node_key) {
+            self.root_mut().increment_version();
+        }
+        self.tombstones.insert(
+            node_key.clone(),
+            Tombstone {
+                version,
+                buried_at: time_t_timestamp(),
+                record_opt,
+            },
+        );
+    }
+
+    pub fn tombstone(&self, node_key: &PublicKey) -> Option<&Tombstone> {
+        self.tombstones.get(node_key)
+    }
+
+    pub fn is_buried(&self, node_key: &PublicKey, version: u32) -> bool {
+        match self.tombstones.get(node_key) {
+            Some(tombstone) => version <= tombstone.version,
+            None => false,
+        }
+    }
+
+    // The signed records of departed Nodes, for passing along in Gossip
+    pub fn tombstone_records(&self) -> Vec<&NodeRecord> {
+        self.tombstones
+            .values()
+            .flat_map(|tombstone| tombstone.record_opt.as_ref())
+            .collect()
+    }
+
+    // Our own neighbors are left alone: we'll hear from the Stream when one of them goes
+    pub fn expire_stale_records(&mut self) {
+        let now = time_t_timestamp();
+        let deadline = now.saturating_sub(NODE_RECORD_TTL_SECS);
+        self.tombstones
+            .retain(|_, tombstone| tombstone.buried_at > deadline);
+        let root = self.root();
+        let to_expire: Vec<(PublicKey, u32)> = self
+            .by_public_key
+            .values()
+            .filter(|node| node.public_key() != root.public_key())
+            .filter(|node| !root.has_half_neighbor(node.public_key()))
+            .filter(|node| node.last_updated() <= deadline)
+            .map(|node| (node.public_key().clone(), node.version()))
+            .collect();
+        if !to_expire.is_empty() {
+            info!(
+                self.logger,
+                "Expiring from the neighborhood {} Node{} not heard from in {} seconds or more: {:?}",
+                to_expire.len(),
+                if to_expire.len() == 1 { "" } else { "s" },
+                NODE_RECORD_TTL_SECS,
+                to_expire.iter().map(|(key, _)| key).collect::<Vec<_>>()
+            );
+            to_expire
+                .into_iter()
+                .for_each(|(key, version)| self.bury(&key, version, None));
+        }
+    }
+
     pub fn to_dot_graph(&self) -> String {
         let renderables = self.to_dot_renderables();
         render_dot_graph(renderables)
@@ -302,9 +409,15 @@ impl NeighborhoodDatabase {
         result
     }
 
+    fn next_generation(&mut self) {
+        self.generation = NEXT_GENERATION.fetch_add(1, Ordering::Relaxed);
+    }
+
     fn add_arbitrary_node(&mut self, node_record: NodeRecord) {
         let public_key = node_record.public_key().clone();
         let node_addr_opt = node_record.node_addr_opt();
+        self.tombstones.remove(&public_key);
+        self.next_generation();
         self.by_public_key.insert(public_key.clone(), node_record);
         if let Some(node_addr) = node_addr_opt {
             self.by_ip_addr.insert(node_addr.ip_addr(), public_key);
@@ -353,6 +466,7 @@ mod tests {
     use crate::test_utils::neighborhood_test_utils::{db_from_node, make_node_record};
     use masq_lib::test_utils::utils::DEFAULT_CHAIN_ID;
     use std::iter::FromIterator;
+    use std::net::SocketAddr;
     use std::str::FromStr;
 
     #[test]
@@ -475,6 +589,69 @@ mod tests {
         assert_eq!(subject.node_by_key(another_node.public_key()), None);
     }
 
+    #[test]
+    fn the_generation_changes_whenever_a_record_may_have() {
+        let this_node = make_node_record(1234, true);
+        let one_node = make_node_record(4567, true);
+        let mut subject = db_from_node(&this_node);
+        let clone = subject.clone();
+        let initial = subject.generation();
+
+        subject.node_by_key(this_node.public_key());
+        let after_reading = subject.generation();
+        subject.add_node(one_node.clone()).unwrap();
+        let after_adding = subject.generation();
+        subject.root_mut();
+        let after_borrowing = subject.generation();
+        subject.remove_node(one_node.public_key());
+        let after_removing = subject.generation();
+
+        assert_eq!(clone.generation(), initial);
+        assert_eq!(after_reading, initial);
+        assert_ne!(after_adding, after_reading);
+        assert_ne!(after_borrowing, after_adding);
+        assert_ne!(after_removing, after_borrowing);
+        assert_ne!(
+            db_from_node(&this_node).generation(),
+            after_removing,
+            "two databases can't share a generation"
+        );
+    }
+
+    #[test]
+    fn removing_a_node_forgets_what_its_record_announced() {
+        let this_node = make_node_record(1234, true);
+        let mut departing_node = make_node_record(7641, true);
+        departing_node.set_features(cipher_suite::local_features());
+        let mut subject = db_from_node(&this_node);
+        subject.add_node(departing_node.clone()).unwrap();
+        cipher_suite::record_peer_features(departing_node.public_key(), departing_node.features());
+        hybrid_kem::record_peer_kem_key(departing_node.public_key(), Some(b"kyber key"));
+        let peer_addr = SocketAddr::new(departing_node.node_addr_opt().unwrap().ip_addr(), 7641);
+        let logger = Logger::new("test");
+        path_mtu::report_black_hole(peer_addr, &logger);
+        let fitted_before = path_mtu::socket_fitted_to_path(peer_addr, &logger).is_some();
+
+        subject.remove_node(departing_node.public_key());
+
+        assert_eq!(
+            cipher_suite::peer_has_feature(
+                departing_node.public_key(),
+                cipher_suite::X25519_CHACHA20_POLY1305
+            ),
+            false
+        );
+        assert_eq!(
+            hybrid_kem::peer_kem_key_opt(departing_node.public_key()),
+            None
+        );
+        assert_eq!(fitted_before, cfg!(target_os = "linux"));
+        assert_eq!(
+            path_mtu::socket_fitted_to_path(peer_addr, &logger).is_some(),
+            false
+        );
+    }
+
     #[test]
     fn node_by_ip_works() {
         let this_node = make_node_record(1234, true);
@@ -888,4 +1065,79 @@ mod tests {
         assert!(subject.node_by_key(referenced_by_dead).is_some());
         assert!(subject.node_by_key(dead).is_none());
     }
+
+    #[test]
+    fn expire_stale_records_buries_nodes_not_heard_from_but_spares_the_root_and_its_neighbors() {
+        let root_node = make_node_record(1234, true);
+        let mut subject: NeighborhoodDatabase = db_from_node(&root_node);
+        let fresh_key = &subject.add_node(make_node_record(2345, true)).unwrap();
+        let stale_key = &subject.add_node(make_node_record(3456, true)).unwrap();
+        let stale_neighbor_key = &subject.add_node(make_node_record(4567, true)).unwrap();
+        let long_ago = time_t_timestamp() - NODE_RECORD_TTL_SECS - 2;
+        subject.root_mut().set_last_updated(long_ago);
+        subject.node_by_key_mut(stale_key).unwrap().set_version(7);
+        subject
+            .node_by_key_mut(stale_key)
+            .unwrap()
+            .set_last_updated(long_ago);
+        subject
+            .node_by_key_mut(stale_neighbor_key)
+            .unwrap()
+            .set_last_updated(long_ago);
+        subject.add_arbitrary_half_neighbor(root_node.public_key(), stale_neighbor_key);
+        subject.add_arbitrary_half_neighbor(stale_key, root_node.public_key());
+
+        subject.expire_stale_records();
+
+        assert!(subject.node_by_key(root_node.public_key()).is_some());
+        assert!(subject.node_by_key(fresh_key).is_some());
+        assert!(subject.node_by_key(stale_neighbor_key).is_some());
+        assert!(subject.node_by_key(stale_key).is_none());
+        assert!(subject.is_buried(stale_key, 7));
+        assert!(!subject.is_buried(stale_key, 8));
+        assert_eq!(subject.tombstone(stale_key).unwrap().record_opt, None);
+        assert!(subject.tombstone_records().is_empty());
+    }
+
+    #[test]
+    fn a_buried_node_is_dropped_from_the_roots_neighbors_and_unburied_when_it_comes_back() {
+        let root_node = make_node_record(1234, true);
+        let mut subject: NeighborhoodDatabase = db_from_node(&root_node);
+        let mut departed = make_node_record(2345, true);
+        subject.add_node(departed.clone()).unwrap();
+        subject.add_arbitrary_full_neighbor(root_node.public_key(), departed.public_key());
+        departed.set_tombstone(true);
+        departed.set_version(3);
+        let root_version = subject.root().version();
+
+        subject.bury(departed.public_key(), 3, Some(departed.clone()));
+
+        assert!(subject.node_by_key(departed.public_key()).is_none());
+        assert!(!subject.root().has_half_neighbor(departed.public_key()));
+        assert_eq!(subject.root().version(), root_version + 1);
+        assert_eq!(subject.tombstone_records(), vec![&departed]);
+
+        departed.set_tombstone(false);
+        departed.set_version(0);
+        subject.add_node(departed.clone()).unwrap();
+
+        assert_eq!(subject.tombstone(departed.public_key()), None);
+    }
+
+    #[test]
+    fn tombstones_expire_with_the_records_they_stand_for() {
+        let root_node = make_node_record(1234, true);
+        let mut subject: NeighborhoodDatabase = db_from_node(&root_node);
+        let old_key = PublicKey::new(&[1, 2, 3]);
+        let new_key = PublicKey::new(&[4, 5, 6]);
+        subject.bury(&old_key, 1, None);
+        subject.bury(&new_key, 1, None);
+        subject.tombstones.get_mut(&old_key).unwrap().buried_at =
+            time_t_timestamp() - NODE_RECORD_TTL_SECS - 2;
+
+        subject.expire_stale_records();
+
+        assert_eq!(subject.tombstone(&old_key), None);
+        assert!(subject.is_buried(&new_key, 1));
+    }
 }
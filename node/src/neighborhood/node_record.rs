@@ -3,16 +3,20 @@
 use crate::blockchain::blockchain_interface::chain_id_from_name;
 use crate::neighborhood::gossip::GossipNodeRecord;
 use crate::neighborhood::neighborhood_database::{NeighborhoodDatabase, NeighborhoodDatabaseError};
-use crate::neighborhood::{regenerate_signed_gossip, AccessibleGossipRecord};
-use crate::sub_lib::cryptde::{CryptDE, CryptData, PlainData, PublicKey};
+use crate::neighborhood::{regenerate_signed_gossip, sign_node_addr, AccessibleGossipRecord};
+use crate::sub_lib::attestation::Attestation;
+use crate::sub_lib::cryptde::{CryptDE, CryptData, KeySuccession, PlainData, PublicKey};
+use crate::sub_lib::link_benchmark::LinkPerformance;
 use crate::sub_lib::neighborhood::NodeDescriptor;
 use crate::sub_lib::neighborhood::RatePack;
 use crate::sub_lib::node_addr::NodeAddr;
 use crate::sub_lib::utils::time_t_timestamp;
-use crate::sub_lib::wallet::Wallet;
+use crate::sub_lib::wallet::{Wallet, WalletChange};
 use masq_lib::constants::DEFAULT_CHAIN_NAME;
+use masq_lib::exit_policy::ExitPolicy;
 use serde_derive::{Deserialize, Serialize};
 use std::collections::btree_set::BTreeSet;
+use std::collections::BTreeMap;
 use std::collections::HashSet;
 use std::convert::TryFrom;
 use std::iter::FromIterator;
@@ -27,6 +31,37 @@ pub struct NodeRecordInner_0v1 {
     pub accepts_connections: bool,
     pub routes_data: bool,
     pub version: u32,
+    // Absent from the records of Nodes that predate exit policies, and left out when empty
+    #[serde(default, skip_serializing_if = "ExitPolicy::is_empty")]
+    pub exit_policy: ExitPolicy,
+    // Present only while a Node that has rotated its key is still answering to the previous one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_succession_opt: Option<KeySuccession>,
+    // Capabilities beyond the ones every Node has, such as additional cipher suites
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub features: BTreeSet<String>,
+    // Present only if the Node has agreed to hybrid post-quantum sealing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kem_public_key_opt: Option<PlainData>,
+    // Present only if the Node checks its IP address against blocklists: the percentage that don't list it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reputation_score_opt: Option<u8>,
+    // Present only if the Node has replaced its earning wallet, so that Nodes that owe it can follow
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wallet_change_opt: Option<WalletChange>,
+    // Present only if the Node benchmarks its links: how each of its neighbors' links performs
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub link_performance: BTreeMap<PublicKey, LinkPerformance>,
+    // Present only if the Node publishes what it has seen of the wallets that owed it payments
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attestations: Vec<Attestation>,
+    // Present only in the last record a departing Node publishes: it is gone, and not to be routed through
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub tombstone: bool,
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
 }
 
 impl TryFrom<GossipNodeRecord> for NodeRecordInner_0v1 {
@@ -59,6 +94,7 @@ pub struct NodeRecord {
     pub metadata: NodeRecordMetadata,
     pub signed_gossip: PlainData,
     pub signature: CryptData,
+    pub node_addr_signature_opt: Option<CryptData>,
 }
 
 impl NodeRecord {
@@ -81,9 +117,19 @@ impl NodeRecord {
                 routes_data,
                 neighbors: BTreeSet::new(),
                 version,
+                exit_policy: ExitPolicy::default(),
+                key_succession_opt: None,
+                features: BTreeSet::new(),
+                kem_public_key_opt: None,
+                reputation_score_opt: None,
+                wallet_change_opt: None,
+                link_performance: BTreeMap::new(),
+                attestations: vec![],
+                tombstone: false,
             },
             signed_gossip: PlainData::new(&[]),
             signature: CryptData::new(&[]),
+            node_addr_signature_opt: None,
         };
         node_record.regenerate_signed_gossip(cryptde);
         node_record
@@ -127,7 +173,8 @@ impl NodeRecord {
     }
 
     pub fn unset_node_addr(&mut self) {
-        self.metadata.node_addr_opt = None
+        self.metadata.node_addr_opt = None;
+        self.node_addr_signature_opt = None;
     }
 
     pub fn half_neighbor_keys(&self) -> HashSet<&PublicKey> {
@@ -195,6 +242,11 @@ impl NodeRecord {
         let (signed_gossip, signature) = regenerate_signed_gossip(&self.inner, cryptde);
         self.signed_gossip = signed_gossip;
         self.signature = signature;
+        self.node_addr_signature_opt = self
+            .metadata
+            .node_addr_opt
+            .as_ref()
+            .map(|node_addr| sign_node_addr(node_addr, self.inner.version, cryptde));
     }
 
     pub fn signed_gossip(&self) -> &PlainData {
@@ -205,6 +257,10 @@ impl NodeRecord {
         &self.signature
     }
 
+    pub fn node_addr_signature_opt(&self) -> Option<&CryptData> {
+        self.node_addr_signature_opt.as_ref()
+    }
+
     pub fn accepts_connections(&self) -> bool {
         self.inner.accepts_connections
     }
@@ -251,6 +307,126 @@ impl NodeRecord {
         &self.inner.rate_pack
     }
 
+    pub fn exit_policy(&self) -> &ExitPolicy {
+        &self.inner.exit_policy
+    }
+
+    pub fn set_exit_policy(&mut self, exit_policy: ExitPolicy) -> bool {
+        if self.inner.exit_policy == exit_policy {
+            false
+        } else {
+            self.inner.exit_policy = exit_policy;
+            true
+        }
+    }
+
+    pub fn key_succession_opt(&self) -> Option<&KeySuccession> {
+        self.inner.key_succession_opt.as_ref()
+    }
+
+    pub fn set_key_succession_opt(&mut self, key_succession_opt: Option<KeySuccession>) -> bool {
+        if self.inner.key_succession_opt == key_succession_opt {
+            false
+        } else {
+            self.inner.key_succession_opt = key_succession_opt;
+            true
+        }
+    }
+
+    pub fn features(&self) -> &BTreeSet<String> {
+        &self.inner.features
+    }
+
+    pub fn set_features(&mut self, features: BTreeSet<String>) -> bool {
+        if self.inner.features == features {
+            false
+        } else {
+            self.inner.features = features;
+            true
+        }
+    }
+
+    pub fn kem_public_key_opt(&self) -> Option<&PlainData> {
+        self.inner.kem_public_key_opt.as_ref()
+    }
+
+    pub fn set_kem_public_key_opt(&mut self, kem_public_key_opt: Option<PlainData>) -> bool {
+        if self.inner.kem_public_key_opt == kem_public_key_opt {
+            false
+        } else {
+            self.inner.kem_public_key_opt = kem_public_key_opt;
+            true
+        }
+    }
+
+    pub fn reputation_score_opt(&self) -> Option<u8> {
+        self.inner.reputation_score_opt
+    }
+
+    pub fn set_reputation_score_opt(&mut self, reputation_score_opt: Option<u8>) -> bool {
+        if self.inner.reputation_score_opt == reputation_score_opt {
+            false
+        } else {
+            self.inner.reputation_score_opt = reputation_score_opt;
+            true
+        }
+    }
+
+    pub fn wallet_change_opt(&self) -> Option<&WalletChange> {
+        self.inner.wallet_change_opt.as_ref()
+    }
+
+    pub fn set_wallet_change_opt(&mut self, wallet_change_opt: Option<WalletChange>) -> bool {
+        if self.inner.wallet_change_opt == wallet_change_opt {
+            false
+        } else {
+            self.inner.wallet_change_opt = wallet_change_opt;
+            true
+        }
+    }
+
+    pub fn link_performance(&self) -> &BTreeMap<PublicKey, LinkPerformance> {
+        &self.inner.link_performance
+    }
+
+    pub fn set_link_performance(
+        &mut self,
+        link_performance: BTreeMap<PublicKey, LinkPerformance>,
+    ) -> bool {
+        if self.inner.link_performance == link_performance {
+            false
+        } else {
+            self.inner.link_performance = link_performance;
+            true
+        }
+    }
+
+    pub fn attestations(&self) -> &[Attestation] {
+        &self.inner.attestations
+    }
+
+    pub fn set_attestations(&mut self, attestations: Vec<Attestation>) -> bool {
+        if self.inner.attestations == attestations {
+            false
+        } else {
+            self.inner.attestations = attestations;
+            true
+        }
+    }
+
+    pub fn is_tombstone(&self) -> bool {
+        self.inner.tombstone
+    }
+
+    pub fn set_tombstone(&mut self, tombstone: bool) -> bool {
+        if self.inner.tombstone == tombstone {
+            false
+        } else {
+            self.inner.tombstone = tombstone;
+            true
+        }
+    }
+
     pub fn is_desirable(&self) -> bool {
         self.metadata.desirable
     }
@@ -286,6 +462,10 @@ impl NodeRecord {
             }
             _ => (),
         }
+        if agr.node_addr_signature_opt.is_some() && self.metadata.node_addr_opt == agr.node_addr_opt
+        {
+            self.node_addr_signature_opt = agr.node_addr_signature_opt;
+        }
         self.metadata.last_update = time_t_timestamp();
         self.signed_gossip = agr.signed_gossip;
         self.signature = agr.signature;
@@ -301,6 +481,7 @@ impl From<AccessibleGossipRecord> for NodeRecord {
             metadata: NodeRecordMetadata::new(),
             signed_gossip: agr.signed_gossip,
             signature: agr.signature,
+            node_addr_signature_opt: agr.node_addr_signature_opt,
         };
         node_record.metadata.node_addr_opt = agr.node_addr_opt;
         node_record
@@ -324,6 +505,7 @@ impl TryFrom<&GossipNodeRecord> for NodeRecord {
             metadata: NodeRecordMetadata::new(),
             signed_gossip: gnr.signed_data.clone(),
             signature: gnr.signature.clone(),
+            node_addr_signature_opt: gnr.node_addr_signature_opt.clone(),
         };
         node_record.metadata.node_addr_opt = gnr.node_addr_opt.clone();
         Ok(node_record)
@@ -964,6 +1146,7 @@ mod tests {
             signed_data: PlainData::new(&[1, 2, 3, 4]),
             signature: CryptData::new(&[]),
             node_addr_opt: None,
+            node_addr_signature_opt: None,
         };
 
         let result = NodeRecordInner_0v1::try_from(corrupt_gnr);
@@ -0,0 +1,452 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use crate::neighborhood::neighborhood_database::NeighborhoodDatabase;
+use crate::sub_lib::cryptde::PublicKey;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::thread;
+
+// Smaller neighborhoods are searched before a thread could even be started
+pub const PARALLEL_SEARCH_MIN_NODES: usize = 1000;
+pub const DEFAULT_ROUTE_SEARCH_THREADS: usize = 4;
+// How many hops longer than the shortest one a route to a particular Node may be. Without a limit,
+// the search would go down every loop-free path in the neighborhood.
+pub const TARGETED_ROUTE_SLACK_HOPS: usize = 2;
+
+// The neighborhood database boiled down to what a route search needs, with the Nodes numbered.
+// It's built once for each generation of the database, instead of at every step of every search.
+pub struct RouteIndex {
+    generation: u64,
+    keys: Vec<PublicKey>,
+    positions: HashMap<PublicKey, usize>,
+    half_neighbors: Vec<Vec<usize>>,
+    full_neighbors: Vec<Vec<usize>>,
+    routes_data: Vec<bool>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RouteQuery {
+    pub prefix: Vec<usize>,
+    pub target_opt: Option<usize>,
+    pub hops_remaining: usize,
+    // On the way back, the target is the originating Node, which is welcome even if it doesn't
+    // route data
+    pub target_always_enterable: bool,
+}
+
+struct SearchPlan {
+    query: RouteQuery,
+    // Nodes that claim the first Node as a neighbor, and so are too close to it to be its exit
+    too_close: Vec<bool>,
+    hops_to_target: Vec<Option<usize>>,
+    max_hops_opt: Option<usize>,
+}
+
+impl RouteIndex {
+    pub fn new(db: &NeighborhoodDatabase) -> RouteIndex {
+        let keys: Vec<PublicKey> = db.keys().into_iter().cloned().collect();
+        let positions: HashMap<PublicKey, usize> = keys
+            .iter()
+            .enumerate()
+            .map(|(position, key)| (key.clone(), position))
+            .collect();
+        let node = |key: &PublicKey| db.node_by_key(key).expect("Node magically disappeared");
+        let half_neighbors: Vec<Vec<usize>> = keys
+            .iter()
+            .map(|key| {
+                node(key)
+                    .half_neighbor_keys()
+                    .into_iter()
+                    .flat_map(|neighbor| positions.get(neighbor).cloned())
+                    .collect()
+            })
+            .collect();
+        let full_neighbors = half_neighbors
+            .iter()
+            .enumerate()
+            .map(|(position, neighbors)| {
+                neighbors
+                    .iter()
+                    .cloned()
+                    .filter(|neighbor| half_neighbors[*neighbor].contains(&position))
+                    .collect()
+            })
+            .collect();
+        let routes_data = keys.iter().map(|key| node(key).routes_data()).collect();
+        RouteIndex {
+            generation: db.generation(),
+            keys,
+            positions,
+            half_neighbors,
+            full_neighbors,
+            routes_data,
+        }
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn position(&self, key: &PublicKey) -> Option<usize> {
+        self.positions.get(key).cloned()
+    }
+
+    pub fn key(&self, position: usize) -> &PublicKey {
+        &self.keys[position]
+    }
+
+    // Every loop-free route that starts with the prefix and either goes at least hops_remaining
+    // more hops to the target, or exactly hops_remaining more hops anywhere if there's no target.
+    // In a big neighborhood, the routes through each of the first hop's candidates are looked for
+    // on as many as max_threads threads at once.
+    pub fn search(
+        index: &Arc<RouteIndex>,
+        query: RouteQuery,
+        max_threads: usize,
+    ) -> Vec<Vec<usize>> {
+        let plan = match index.plan(query) {
+            Some(plan) => Arc::new(plan),
+            None => return vec![],
+        };
+        let mut on_path = vec![false; index.node_count()];
+        plan.query
+            .prefix
+            .iter()
+            .for_each(|position| on_path[*position] = true);
+        if index.completes(&plan, &plan.query.prefix, plan.query.hops_remaining) {
+            return vec![plan.query.prefix.clone()];
+        }
+        let first_hops = index.next_hops(
+            &plan,
+            &plan.query.prefix,
+            &on_path,
+            plan.query.hops_remaining,
+        );
+        let thread_count = index.search_thread_count(max_threads, first_hops.len());
+        if thread_count < 2 {
+            return index.search_through(&plan, &first_hops);
+        }
+        let handles = (0..thread_count)
+            .map(|thread_number| {
+                let share: Vec<usize> = first_hops
+                    .iter()
+                    .skip(thread_number)
+                    .step_by(thread_count)
+                    .cloned()
+                    .collect();
+                let index = index.clone();
+                let plan = plan.clone();
+                thread::spawn(move || index.search_through(&plan, &share))
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("Route search thread panicked"))
+            .collect()
+    }
+
+    fn search_thread_count(&self, max_threads: usize, first_hop_count: usize) -> usize {
+        if self.node_count() < PARALLEL_SEARCH_MIN_NODES {
+            1
+        } else {
+            max_threads.min(first_hop_count).max(1)
+        }
+    }
+
+    fn plan(&self, query: RouteQuery) -> Option<SearchPlan> {
+        let first = *query.prefix.first().expect("Empty prefix");
+        let last = *query.prefix.last().expect("Empty prefix");
+        let too_close = self
+            .half_neighbors
+            .iter()
+            .map(|neighbors| neighbors.contains(&first))
+            .collect();
+        let (hops_to_target, max_hops_opt) = match query.target_opt {
+            None => (vec![], None),
+            Some(target) => {
+                let enterable = self.routes_data[target] || query.target_always_enterable;
+                let hops_to_target = self.hops_to(target, enterable);
+                let shortest = hops_to_target[last]?;
+                let max_hops = (query.prefix.len() - 1)
+                    + shortest.max(query.hops_remaining)
+                    + TARGETED_ROUTE_SLACK_HOPS;
+                (hops_to_target, Some(max_hops))
+            }
+        };
+        Some(SearchPlan {
+            query,
+            too_close,
+            hops_to_target,
+            max_hops_opt,
+        })
+    }
+
+    // The fewest hops from each Node to the target, relaying only through Nodes that route data
+    fn hops_to(&self, target: usize, enterable: bool) -> Vec<Option<usize>> {
+        let mut hops = vec![None; self.node_count()];
+        hops[target] = Some(0);
+        if !enterable {
+            return hops;
+        }
+        let mut queue = VecDeque::new();
+        queue.push_back((target, 0));
+        while let Some((position, distance)) = queue.pop_front() {
+            for neighbor in &self.full_neighbors[position] {
+                if hops[*neighbor].is_none() {
+                    hops[*neighbor] = Some(distance + 1);
+                    if self.routes_data[*neighbor] {
+                        queue.push_back((*neighbor, distance + 1));
+                    }
+                }
+            }
+        }
+        hops
+    }
+
+    fn search_through(&self, plan: &SearchPlan, first_hops: &[usize]) -> Vec<Vec<usize>> {
+        let mut path = plan.query.prefix.clone();
+        let mut on_path = vec![false; self.node_count()];
+        path.iter().for_each(|position| on_path[*position] = true);
+        let mut routes = vec![];
+        for next in first_hops {
+            path.push(*next);
+            on_path[*next] = true;
+            self.extend(
+                plan,
+                &mut path,
+                &mut on_path,
+                plan.query.hops_remaining.saturating_sub(1),
+                &mut routes,
+            );
+            on_path[*next] = false;
+            path.pop();
+        }
+        routes
+    }
+
+    fn extend(
+        &self,
+        plan: &SearchPlan,
+        path: &mut Vec<usize>,
+        on_path: &mut [bool],
+        hops_remaining: usize,
+        routes: &mut Vec<Vec<usize>>,
+    ) {
+        if self.completes(plan, path, hops_remaining) {
+            routes.push(path.clone());
+            return;
+        }
+        for next in self.next_hops(plan, path, on_path, hops_remaining) {
+            path.push(next);
+            on_path[next] = true;
+            self.extend(
+                plan,
+                path,
+                on_path,
+                hops_remaining.saturating_sub(1),
+                routes,
+            );
+            on_path[next] = false;
+            path.pop();
+        }
+    }
+
+    fn completes(&self, plan: &SearchPlan, path: &[usize], hops_remaining: usize) -> bool {
+        let last = *path.last().expect("Empty path");
+        hops_remaining == 0
+            && plan.query.target_opt.map_or(true, |target| target == last)
+            // Zero- and single-hop routes are not subject to exit-too-close restrictions
+            && (path.len() <= 2 || !plan.too_close[last])
+    }
+
+    fn next_hops(
+        &self,
+        plan: &SearchPlan,
+        path: &[usize],
+        on_path: &[bool],
+        hops_remaining: usize,
+    ) -> Vec<usize> {
+        let last = *path.last().expect("Empty path");
+        if hops_remaining == 0 && plan.query.target_opt.is_none() {
+            // don't continue a targetless search past the minimum hop count
+            return vec![];
+        }
+        if Some(last) == plan.query.target_opt {
+            // The target can't be reached again without a loop
+            return vec![];
+        }
+        self.full_neighbors[last]
+            .iter()
+            .cloned()
+            .filter(|next| !on_path[*next])
+            .filter(|next| {
+                self.routes_data[*next]
+                    || (plan.query.target_always_enterable && Some(*next) == plan.query.target_opt)
+            })
+            .filter(|next| match plan.max_hops_opt {
+                Some(max_hops) => plan.hops_to_target[*next]
+                    .map(|hops| path.len() + hops <= max_hops)
+                    .unwrap_or(false),
+                None => true,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::neighborhood_test_utils::{db_from_node, make_node_record};
+
+    // A ring of Nodes, each of which is also neighbors with the Nodes a few places along the ring
+    fn ring_db(node_count: u16) -> (NeighborhoodDatabase, Vec<PublicKey>) {
+        let root = make_node_record(1000, true);
+        let mut db = db_from_node(&root);
+        let mut keys = vec![root.public_key().clone()];
+        (1..node_count).for_each(|n| {
+            keys.push(db.add_node(make_node_record(1000 + n, true)).unwrap());
+        });
+        (0..keys.len()).for_each(|n| {
+            vec![1, 7, 31].into_iter().for_each(|distance| {
+                db.add_arbitrary_full_neighbor(&keys[n], &keys[(n + distance) % keys.len()]);
+            })
+        });
+        (db, keys)
+    }
+
+    fn query(
+        index: &RouteIndex,
+        prefix: &[&PublicKey],
+        target_opt: Option<&PublicKey>,
+        hops_remaining: usize,
+    ) -> RouteQuery {
+        RouteQuery {
+            prefix: prefix
+                .iter()
+                .map(|key| index.position(key).unwrap())
+                .collect(),
+            target_opt: target_opt.map(|key| index.position(key).unwrap()),
+            hops_remaining,
+            target_always_enterable: false,
+        }
+    }
+
+    fn keys_of(index: &RouteIndex, routes: Vec<Vec<usize>>) -> Vec<Vec<PublicKey>> {
+        let mut result: Vec<Vec<PublicKey>> = routes
+            .into_iter()
+            .map(|route| {
+                route
+                    .into_iter()
+                    .map(|position| index.key(position).clone())
+                    .collect()
+            })
+            .collect();
+        result.sort();
+        result
+    }
+
+    #[test]
+    fn the_index_knows_only_full_neighbors_as_neighbors() {
+        let root = make_node_record(1234, true);
+        let mut db = db_from_node(&root);
+        let full = db.add_node(make_node_record(2345, true)).unwrap();
+        let half = db.add_node(make_node_record(3456, false)).unwrap();
+        db.add_arbitrary_full_neighbor(root.public_key(), &full);
+        db.add_arbitrary_half_neighbor(root.public_key(), &half);
+
+        let subject = RouteIndex::new(&db);
+
+        let root_position = subject.position(root.public_key()).unwrap();
+        let full_position = subject.position(&full).unwrap();
+        assert_eq!(subject.generation(), db.generation());
+        assert_eq!(subject.node_count(), 3);
+        assert_eq!(subject.full_neighbors[root_position], vec![full_position]);
+        assert_eq!(subject.half_neighbors[root_position].len(), 2);
+        assert_eq!(subject.key(full_position), &full);
+    }
+
+    #[test]
+    fn parallel_and_single_threaded_searches_find_the_same_routes() {
+        let (db, keys) = ring_db(PARALLEL_SEARCH_MIN_NODES as u16 + 10);
+        let index = Arc::new(RouteIndex::new(&db));
+        let query = query(&index, &[&keys[0]], None, 3);
+        let plan = index.plan(query.clone()).unwrap();
+        let mut on_path = vec![false; index.node_count()];
+        on_path[query.prefix[0]] = true;
+        let first_hops = index.next_hops(&plan, &query.prefix, &on_path, 3);
+        let single = index.search_through(&plan, &first_hops);
+
+        let parallel = RouteIndex::search(&index, query, DEFAULT_ROUTE_SEARCH_THREADS);
+
+        assert_eq!(first_hops.len(), 6);
+        assert!(!single.is_empty());
+        assert_eq!(keys_of(&index, parallel), keys_of(&index, single));
+    }
+
+    #[test]
+    fn a_big_neighborhood_is_searched_on_no_more_threads_than_configured() {
+        let (big_db, _) = ring_db(PARALLEL_SEARCH_MIN_NODES as u16 + 10);
+        let (small_db, _) = ring_db(200);
+        let big = RouteIndex::new(&big_db);
+        let small = RouteIndex::new(&small_db);
+
+        assert_eq!(big.search_thread_count(2, 6), 2);
+        assert_eq!(big.search_thread_count(16, 6), 6);
+        assert_eq!(big.search_thread_count(1, 6), 1);
+        assert_eq!(small.search_thread_count(16, 6), 1);
+    }
+
+    #[test]
+    fn targeted_routes_are_no_more_than_a_little_longer_than_the_shortest() {
+        let (db, keys) = ring_db(200);
+        let index = Arc::new(RouteIndex::new(&db));
+
+        let routes = RouteIndex::search(
+            &index,
+            query(&index, &[&keys[0]], Some(&keys[2]), 1),
+            DEFAULT_ROUTE_SEARCH_THREADS,
+        );
+
+        // The shortest route is two hops along the ring
+        assert!(!routes.is_empty());
+        assert!(routes
+            .iter()
+            .all(|route| route.len() - 1 <= 2 + TARGETED_ROUTE_SLACK_HOPS));
+        assert!(routes.iter().any(|route| route.len() - 1 == 2));
+        assert!(routes
+            .iter()
+            .all(|route| route.last() == index.position(&keys[2]).as_ref()));
+    }
+
+    // How long this takes is measured by benches/route_search.rs
+    #[test]
+    fn routes_through_a_few_thousand_nodes_are_found() {
+        let (db, keys) = ring_db(3000);
+        let index = Arc::new(RouteIndex::new(&db));
+
+        let over = RouteIndex::search(
+            &index,
+            query(&index, &[&keys[0]], None, 3),
+            DEFAULT_ROUTE_SEARCH_THREADS,
+        );
+        let back = RouteIndex::search(
+            &index,
+            RouteQuery {
+                target_always_enterable: true,
+                ..query(&index, &[&keys[93]], Some(&keys[0]), 3)
+            },
+            DEFAULT_ROUTE_SEARCH_THREADS,
+        );
+
+        assert_eq!(index.node_count(), 3000);
+        assert!(!over.is_empty());
+        assert!(over.iter().all(|route| route.len() == 4));
+        assert!(!back.is_empty());
+        assert!(back
+            .iter()
+            .all(|route| route.last() == index.position(&keys[0]).as_ref()));
+    }
+}
@@ -0,0 +1,163 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use crate::neighborhood::truncated_key;
+use crate::sub_lib::cryptde::PublicKey;
+use crate::sub_lib::link_benchmark::LinkPerformance;
+use crate::sub_lib::route::RouteSegment;
+use masq_lib::messages::{UiSpeedTestResponse, UiSpeedTestResult};
+use std::time::Duration;
+
+// One hop count's part of a speed test: a route to an exit that answers probes and back again,
+// and what has been measured on it so far
+pub struct SpeedTestLeg {
+    pub hop_count: usize,
+    pub over: RouteSegment,
+    pub back: RouteSegment,
+    empty_round_trip_opt: Option<Duration>,
+    performance_opt: Option<LinkPerformance>,
+}
+
+impl SpeedTestLeg {
+    pub fn new(hop_count: usize, over: RouteSegment, back: RouteSegment) -> Self {
+        Self {
+            hop_count,
+            over,
+            back,
+            empty_round_trip_opt: None,
+            performance_opt: None,
+        }
+    }
+
+    pub fn exit_key(&self) -> &PublicKey {
+        self.over.keys.last().expect("Empty segment")
+    }
+
+    // True if it was the empty probe that came back, and the padded one should go out now
+    pub fn record_round_trip(
+        &mut self,
+        padded: bool,
+        round_trip: Duration,
+        padding_bytes: usize,
+    ) -> bool {
+        if !padded {
+            self.empty_round_trip_opt = Some(round_trip);
+            return true;
+        }
+        if let Some(empty_round_trip) = self.empty_round_trip_opt {
+            self.performance_opt = Some(LinkPerformance::from_round_trips(
+                empty_round_trip,
+                round_trip,
+                padding_bytes,
+            ));
+        }
+        false
+    }
+}
+
+// A UI's request to measure routes of several hop counts, answered when every leg has been
+// measured or when it times out, whichever comes first
+pub struct SpeedTest {
+    pub client_id: u64,
+    pub context_id: u64,
+    pub legs: Vec<SpeedTestLeg>,
+    pub unavailable_hop_counts: Vec<usize>,
+}
+
+impl SpeedTest {
+    pub fn new(client_id: u64, context_id: u64) -> Self {
+        Self {
+            client_id,
+            context_id,
+            legs: vec![],
+            unavailable_hop_counts: vec![],
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.legs.iter().all(|leg| leg.performance_opt.is_some())
+    }
+
+    // Legs still waiting on their probes count as unavailable
+    pub fn response(&self) -> UiSpeedTestResponse {
+        let mut unavailable_hop_counts = self.unavailable_hop_counts.clone();
+        let mut results = vec![];
+        self.legs.iter().for_each(|leg| match leg.performance_opt {
+            Some(performance) => results.push(UiSpeedTestResult {
+                hop_count: leg.hop_count,
+                exit_key: truncated_key(leg.exit_key()),
+                latency_ms: performance.latency_ms,
+                throughput_kbps: performance.throughput_kbps,
+            }),
+            None => unavailable_hop_counts.push(leg.hop_count),
+        });
+        results.sort_by_key(|result| result.hop_count);
+        unavailable_hop_counts.sort_unstable();
+        UiSpeedTestResponse {
+            results,
+            unavailable_hop_counts,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sub_lib::dispatcher::Component;
+
+    fn make_leg(hop_count: usize, exit: &PublicKey) -> SpeedTestLeg {
+        let origin = PublicKey::new(b"origin");
+        SpeedTestLeg::new(
+            hop_count,
+            RouteSegment::new(vec![&origin, exit], Component::Neighborhood),
+            RouteSegment::new(vec![exit, &origin], Component::Neighborhood),
+        )
+    }
+
+    #[test]
+    fn a_leg_is_measured_once_its_padded_probe_follows_its_empty_one_back() {
+        let mut subject = make_leg(1, &PublicKey::new(b"exit"));
+
+        let padded_first = subject.record_round_trip(true, Duration::from_millis(90), 1024);
+        let empty = subject.record_round_trip(false, Duration::from_millis(40), 1024);
+        let padded = subject.record_round_trip(true, Duration::from_millis(42), 1024);
+
+        assert_eq!(padded_first, false);
+        assert_eq!(empty, true);
+        assert_eq!(padded, false);
+        assert_eq!(
+            subject.performance_opt,
+            Some(LinkPerformance {
+                latency_ms: 40,
+                throughput_kbps: 512,
+            })
+        );
+    }
+
+    #[test]
+    fn the_response_reports_measured_legs_and_counts_the_rest_as_unavailable() {
+        let exit = PublicKey::new(b"exit key");
+        let mut subject = SpeedTest::new(1234, 4321);
+        subject.unavailable_hop_counts.push(3);
+        subject.legs.push(make_leg(2, &exit));
+        subject.legs.push(make_leg(1, &exit));
+        subject.legs[1].record_round_trip(false, Duration::from_millis(30), 2048);
+        let before = subject.is_finished();
+        subject.legs[1].record_round_trip(true, Duration::from_millis(32), 2048);
+
+        let result = subject.response();
+
+        assert_eq!(before, false);
+        assert_eq!(subject.is_finished(), false);
+        assert_eq!(
+            result,
+            UiSpeedTestResponse {
+                results: vec![UiSpeedTestResult {
+                    hop_count: 1,
+                    exit_key: truncated_key(&exit),
+                    latency_ms: 30,
+                    throughput_kbps: 1024,
+                }],
+                unavailable_hop_counts: vec![2, 3],
+            }
+        );
+    }
+}
@@ -0,0 +1,68 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use crate::sub_lib::proxy_server::DEFAULT_MINIMUM_HOP_COUNT;
+use masq_lib::messages::UiWarmUpBroadcast;
+
+// Most of the bar fills as Nodes are heard of, up to as many as a route of the minimum hop count
+// needs; a full neighbor fills a little more. The rest waits for a route that can actually be made,
+// which also needs those Nodes to be connected the right way.
+const NODES_KNOWN_PERCENT: usize = 60;
+const NEIGHBOR_PERCENT: usize = 20;
+
+// nodes_known doesn't count this Node itself
+pub fn warm_up_progress(
+    nodes_known: usize,
+    neighbors: usize,
+    routes_available: bool,
+) -> UiWarmUpBroadcast {
+    let progress = if routes_available {
+        100
+    } else {
+        NODES_KNOWN_PERCENT * nodes_known.min(DEFAULT_MINIMUM_HOP_COUNT) / DEFAULT_MINIMUM_HOP_COUNT
+            + if neighbors > 0 { NEIGHBOR_PERCENT } else { 0 }
+    };
+    UiWarmUpBroadcast {
+        nodes_known: nodes_known as u64,
+        neighbors: neighbors as u64,
+        min_hops_satisfiable: nodes_known >= DEFAULT_MINIMUM_HOP_COUNT,
+        routes_available,
+        progress: progress as u8,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progress_grows_with_nodes_and_neighbors_but_only_a_route_completes_it() {
+        let progress = |nodes_known, neighbors, routes_available| {
+            warm_up_progress(nodes_known, neighbors, routes_available).progress
+        };
+
+        assert_eq!(progress(0, 0, false), 0);
+        assert_eq!(progress(1, 0, false), 20);
+        assert_eq!(progress(1, 1, false), 40);
+        assert_eq!(progress(2, 1, false), 60);
+        assert_eq!(progress(3, 1, false), 80);
+        assert_eq!(progress(50, 1, false), 80);
+        assert_eq!(progress(3, 1, true), 100);
+    }
+
+    #[test]
+    fn min_hops_are_satisfiable_once_enough_nodes_are_known() {
+        assert_eq!(
+            warm_up_progress(DEFAULT_MINIMUM_HOP_COUNT - 1, 1, false),
+            UiWarmUpBroadcast {
+                nodes_known: DEFAULT_MINIMUM_HOP_COUNT as u64 - 1,
+                neighbors: 1,
+                min_hops_satisfiable: false,
+                routes_available: false,
+                progress: 60,
+            }
+        );
+        assert_eq!(
+            warm_up_progress(DEFAULT_MINIMUM_HOP_COUNT, 0, false).min_hops_satisfiable,
+            true
+        );
+    }
+}
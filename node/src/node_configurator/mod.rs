@@ -4,16 +4,21 @@ pub mod node_configurator_generate_wallet;
 pub mod node_configurator_initialization;
 pub mod node_configurator_recover_wallet;
 pub mod node_configurator_standard;
+pub mod prompt_backend;
 
 use crate::blockchain::bip32::Bip32ECKeyPair;
 use crate::blockchain::bip39::Bip39;
 use crate::blockchain::blockchain_interface::chain_id_from_name;
 use crate::bootstrapper::RealUser;
 use crate::database::db_initializer::{DbInitializer, DbInitializerReal, DATABASE_FILE};
+use crate::db_config::password_budget::check_password_within_budget;
 use crate::db_config::persistent_configuration::{
     PersistentConfigError, PersistentConfiguration, PersistentConfigurationReal,
 };
+use crate::node_configurator::prompt_backend::{make_prompt_backend, PromptBackend};
 use crate::sub_lib::cryptde::PlainData;
+use crate::sub_lib::redacted::Redacted;
+use crate::sub_lib::ui_gateway::{UiAuthConfig, UiTlsConfig};
 use crate::sub_lib::utils::make_new_multi_config;
 use crate::sub_lib::wallet::Wallet;
 use crate::sub_lib::wallet::{DEFAULT_CONSUMING_DERIVATION_PATH, DEFAULT_EARNING_DERIVATION_PATH};
@@ -22,20 +27,22 @@ use clap::{crate_description, value_t, App, AppSettings, Arg};
 use dirs::{data_local_dir, home_dir};
 use masq_lib::command::StdStreams;
 use masq_lib::constants::DEFAULT_CHAIN_NAME;
+use masq_lib::ip_network::IpNetwork;
 use masq_lib::multi_config::{merge, CommandLineVcl, EnvironmentVcl, MultiConfig, VclArg};
 use masq_lib::shared_schema::{
     chain_arg, config_file_arg, data_directory_arg, real_user_arg, ConfiguratorError,
 };
 use masq_lib::test_utils::fake_stream_holder::FakeStreamHolder;
 use masq_lib::utils::localhost;
-use rpassword::read_password_with_reader;
 use rustc_hex::FromHex;
+use std::cell::RefCell;
 use std::fmt::Debug;
 use std::io;
 use std::io::Read;
-use std::net::{SocketAddr, TcpListener};
-use std::path::PathBuf;
+use std::net::{IpAddr, SocketAddr, TcpListener};
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::time::SystemTime;
 use tiny_hderive::bip44::DerivationPath;
 
 pub trait NodeConfigurator<T> {
@@ -163,7 +170,7 @@ pub fn determine_config_file_path(
     let (real_user, data_directory_opt, chain_name) =
         real_user_data_directory_opt_and_chain_name(dirs_wrapper, &multi_config);
     let directory =
-        data_directory_from_context(dirs_wrapper, &real_user, &data_directory_opt, &chain_name);
+        data_directory_from_context(dirs_wrapper, &real_user, &data_directory_opt, &chain_name)?;
     Ok((directory.join(config_file_path), user_specified))
 }
 
@@ -202,17 +209,18 @@ pub fn create_wallet(
 pub fn initialize_database(
     data_directory: &PathBuf,
     chain_id: u8,
-) -> Box<dyn PersistentConfiguration> {
-    let conn = DbInitializerReal::new()
-        .initialize(data_directory, chain_id, true)
-        .unwrap_or_else(|e| {
-            panic!(
+) -> Result<Box<dyn PersistentConfiguration>, ConfiguratorError> {
+    match DbInitializerReal::new().initialize(data_directory, chain_id, true) {
+        Ok(conn) => Ok(Box::new(PersistentConfigurationReal::from(conn))),
+        Err(e) => Err(ConfiguratorError::required(
+            "data-directory",
+            &format!(
                 "Can't initialize database at {:?}: {:?}",
                 data_directory.join(DATABASE_FILE),
                 e
-            )
-        });
-    Box::new(PersistentConfigurationReal::from(conn))
+            ),
+        )),
+    }
 }
 
 pub fn update_db_password(
@@ -249,31 +257,42 @@ pub fn data_directory_from_context(
     real_user: &RealUser,
     data_directory_opt: &Option<PathBuf>,
     chain_name: &str,
-) -> PathBuf {
+) -> Result<PathBuf, ConfiguratorError> {
     match data_directory_opt {
-        Some(data_directory) => data_directory.clone(),
+        Some(data_directory) => Ok(data_directory.clone()),
         None => {
-            let right_home_dir = real_user
-                .home_dir_opt
-                .as_ref()
-                .expect("No real-user home directory; specify --real-user")
-                .to_string_lossy()
-                .to_string();
-            let wrong_home_dir = dirs_wrapper
-                .home_dir()
-                .expect("No privileged home directory; specify --data-directory")
-                .to_string_lossy()
-                .to_string();
-            let wrong_local_data_dir = dirs_wrapper
-                .data_dir()
-                .expect("No privileged local data directory; specify --data-directory")
-                .to_string_lossy()
-                .to_string();
+            let right_home_dir = match real_user.home_dir_opt.as_ref() {
+                Some(home_dir) => home_dir.to_string_lossy().to_string(),
+                None => {
+                    return Err(ConfiguratorError::required(
+                        "real-user",
+                        "No real-user home directory; specify --real-user",
+                    ))
+                }
+            };
+            let wrong_home_dir = match dirs_wrapper.home_dir() {
+                Some(home_dir) => home_dir.to_string_lossy().to_string(),
+                None => {
+                    return Err(ConfiguratorError::required(
+                        "data-directory",
+                        "No privileged home directory; specify --data-directory",
+                    ))
+                }
+            };
+            let wrong_local_data_dir = match dirs_wrapper.data_dir() {
+                Some(data_dir) => data_dir.to_string_lossy().to_string(),
+                None => {
+                    return Err(ConfiguratorError::required(
+                        "data-directory",
+                        "No privileged local data directory; specify --data-directory",
+                    ))
+                }
+            };
             let right_local_data_dir =
                 wrong_local_data_dir.replace(&wrong_home_dir, &right_home_dir);
-            PathBuf::from(right_local_data_dir)
+            Ok(PathBuf::from(right_local_data_dir)
                 .join("MASQ")
-                .join(chain_name)
+                .join(chain_name))
         }
     }
 }
@@ -300,8 +319,8 @@ pub fn prepare_initialization_mode<'a>(
         &real_user,
         &data_directory_opt,
         &chain_name,
-    );
-    let persistent_config_box = initialize_database(&directory, chain_id_from_name(&chain_name));
+    )?;
+    let persistent_config_box = initialize_database(&directory, chain_id_from_name(&chain_name))?;
     Ok((multi_config, persistent_config_box))
 }
 
@@ -318,8 +337,36 @@ pub fn check_for_past_initialization(
     }
 }
 
+// Paths that pass validation can still fail to derive a key, and there's nothing wrong with the
+// Node when they do
+pub fn derive_wallet(
+    seed: &PlainData,
+    derivation_path: &str,
+    parameter: &str,
+) -> Result<Wallet, ConfiguratorError> {
+    match Bip32ECKeyPair::from_raw(seed.as_slice(), derivation_path) {
+        Ok(keypair) => Ok(Wallet::from(keypair)),
+        Err(e) => Err(ConfiguratorError::failed(
+            parameter,
+            &format!(
+                "Couldn't make key pair from derivation path '{}': {}",
+                Redacted(derivation_path),
+                e
+            ),
+        )),
+    }
+}
+
+// The backend chosen with --password-prompt; the console if none was
+pub fn prompt_backend(multi_config: &MultiConfig) -> Box<dyn PromptBackend> {
+    make_prompt_backend(
+        &value_m!(multi_config, "password-prompt", String).unwrap_or_else(|| "tty".to_string()),
+    )
+}
+
 pub fn request_new_db_password(
     streams: &mut StdStreams,
+    backend: &dyn PromptBackend,
     possible_preamble: Option<&str>,
     prompt: &str,
     confirmation_prompt: &str,
@@ -327,8 +374,11 @@ pub fn request_new_db_password(
     if let Some(preamble) = possible_preamble {
         flushed_write(streams.stdout, &format!("{}\n", preamble));
     }
-    match request_password_with_retry(prompt, streams, |streams| {
+    match request_password_with_retry(streams, backend, |streams| {
         request_password_with_confirmation(
+            backend,
+            "db-password",
+            prompt,
             confirmation_prompt,
             "Passwords do not match.",
             streams,
@@ -351,9 +401,10 @@ pub fn request_new_db_password(
 
 pub fn request_existing_db_password(
     streams: &mut StdStreams,
+    backend: &dyn PromptBackend,
     possible_preamble: Option<&str>,
     prompt: &str,
-    persistent_config: &dyn PersistentConfiguration,
+    persistent_config: &mut dyn PersistentConfiguration,
 ) -> Result<Option<String>, ConfiguratorError> {
     match persistent_config.check_password(None) {
         Ok(true) => return Ok(None),
@@ -363,13 +414,20 @@ pub fn request_existing_db_password(
     if let Some(preamble) = possible_preamble {
         flushed_write(streams.stdout, &format!("{}\n", preamble))
     };
+    // Every try counts against the database's password budget, not just this run's attempts
+    let persistent_config = RefCell::new(persistent_config);
+    let persistent_config_ref = &persistent_config;
     let verifier = move |password: &str| {
         if password.is_empty() {
             return Err(PasswordVerificationError::YourFault(
                 "Password must not be blank.".to_string(),
             ));
         }
-        match persistent_config.check_password(Some(password)) {
+        match check_password_within_budget(
+            &mut **persistent_config_ref.borrow_mut(),
+            password,
+            SystemTime::now(),
+        ) {
             Ok(true) => Ok(()),
             Ok(false) => Err(PasswordVerificationError::YourFault(
                 "Incorrect password.".to_string(),
@@ -377,8 +435,8 @@ pub fn request_existing_db_password(
             Err(pce) => Err(PasswordVerificationError::MyFault(pce)),
         }
     };
-    let result = match request_password_with_retry(prompt, streams, |streams| {
-        request_existing_password(streams, verifier)
+    let result = match request_password_with_retry(streams, backend, |streams| {
+        request_existing_password(backend, "db-password", prompt, streams, verifier)
     }) {
         Ok(ref password) if password.is_empty() => None,
         Ok(password) => Some(password),
@@ -419,14 +477,18 @@ pub enum PasswordVerificationError {
 }
 
 pub fn request_existing_password<F>(
+    backend: &dyn PromptBackend,
+    parameter: &str,
+    prompt: &str,
     streams: &mut StdStreams,
     verifier: F,
 ) -> Result<String, PasswordError>
 where
     F: FnOnce(&str) -> Result<(), PasswordVerificationError>,
 {
-    let reader_opt = possible_reader_from_stream(streams);
-    let password = read_password_with_reader(reader_opt).expect("Fatal error");
+    let password = backend
+        .read_password(parameter, prompt, streams)
+        .map_err(PasswordError::VerifyError)?;
     match verifier(&password) {
         Ok(_) => Ok(password),
         Err(PasswordVerificationError::YourFault(msg)) => Err(PasswordError::VerifyError(msg)),
@@ -436,6 +498,9 @@ where
 
 // require two matching entries
 pub fn request_password_with_confirmation<F>(
+    backend: &dyn PromptBackend,
+    parameter: &str,
+    prompt: &str,
     confirmation_prompt: &str,
     mismatch_msg: &str,
     streams: &mut StdStreams,
@@ -444,17 +509,18 @@ pub fn request_password_with_confirmation<F>(
 where
     F: FnOnce(&str) -> Result<(), String>,
 {
-    let reader_opt = possible_reader_from_stream(streams);
-    let password = read_password_with_reader(reader_opt).expect("Fatal error");
+    let password = backend
+        .read_password(parameter, prompt, streams)
+        .map_err(PasswordError::VerifyError)?;
     match verifier(&password) {
         Ok(_) => {
-            flushed_write(streams.stdout, confirmation_prompt);
-            let reader_opt = possible_reader_from_stream(streams);
-            let confirm = read_password_with_reader(reader_opt).expect("Fatal error");
+            let confirm = backend
+                .read_password(parameter, confirmation_prompt, streams)
+                .map_err(PasswordError::VerifyError)?;
             if password == confirm {
                 Ok(password)
             } else {
-                flushed_write(streams.stdout, mismatch_msg);
+                backend.complain(mismatch_msg, streams);
                 Err(PasswordError::Mismatch)
             }
         }
@@ -462,28 +528,42 @@ where
     }
 }
 
+// For when the user runs out of tries at confirming a mnemonic passphrase
+pub fn mnemonic_passphrase_error(e: PasswordError) -> ConfiguratorError {
+    match e {
+        PasswordError::InternalError(pce) => pce.into_configurator_error("mnemonic-passphrase"),
+        _ => {
+            ConfiguratorError::required("mnemonic-passphrase", "Mnemonic passphrases did not match")
+        }
+    }
+}
+
+// The requester shows its own prompts through the backend, which decides how many tries it gets
 pub fn request_password_with_retry<R>(
-    prompt: &str,
     streams: &mut StdStreams,
+    backend: &dyn PromptBackend,
     requester: R,
 ) -> Result<String, PasswordError>
 where
     R: Fn(&mut StdStreams) -> Result<String, PasswordError>,
 {
-    for attempt in &["Try again.", "Try again.", "Giving up."] {
-        flushed_write(streams.stdout, prompt);
+    let attempts = backend.attempts();
+    for attempt in 1..=attempts {
+        let next = if attempt < attempts {
+            "Try again."
+        } else {
+            "Giving up."
+        };
         match requester(streams) {
             Ok(password) => return Ok(password),
-            Err(PasswordError::Mismatch) => {
-                flushed_write(streams.stdout, &format!(" {}\n", attempt))
-            }
+            Err(PasswordError::Mismatch) => backend.complain(&format!(" {}\n", next), streams),
             Err(PasswordError::VerifyError(msg)) => {
-                flushed_write(streams.stdout, &format!("{} {}\n", msg, attempt))
+                backend.complain(&format!("{} {}\n", msg, next), streams)
             }
             Err(PasswordError::InternalError(pce)) => {
                 return Err(PasswordError::InternalError(pce))
             }
-            Err(e) => flushed_write(streams.stdout, &format!("{:?} {}\n", e, attempt)),
+            Err(e) => backend.complain(&format!("{:?} {}\n", e, next), streams),
         }
     }
     Err(PasswordError::RetriesExhausted)
@@ -523,6 +603,64 @@ pub fn flushed_write(target: &mut dyn io::Write, string: &str) {
     target.flush().expect("Failed flush.");
 }
 
+pub const UI_TLS_CERTIFICATE_FILE: &str = "ui-tls-certificate.pem";
+pub const UI_TLS_KEY_FILE: &str = "ui-tls-key.pem";
+
+// Without a certificate of the user's own, a self-signed one is kept in default_directory
+pub fn ui_tls_config_from(
+    multi_config: &MultiConfig,
+    default_directory: &Path,
+) -> Option<UiTlsConfig> {
+    match value_m!(multi_config, "ui-tls", String) {
+        Some(ref value) if value.eq_ignore_ascii_case("on") => (),
+        _ => return None,
+    }
+    let interfaces = match value_m!(multi_config, "ui-interfaces", String) {
+        Some(joined_interfaces) => joined_interfaces
+            .split(',')
+            .map(|ip_str| IpAddr::from_str(ip_str).expect("Bad clap validation for ui-interfaces"))
+            .collect(),
+        None => vec![],
+    };
+    let allowlist = match value_m!(multi_config, "ui-allowlist", String) {
+        Some(joined_networks) => joined_networks
+            .split(',')
+            .map(|network_str| {
+                IpNetwork::from_str(network_str).expect("Bad clap validation for ui-allowlist")
+            })
+            .collect(),
+        None => vec![],
+    };
+    let client_ca_path_opt = value_m!(multi_config, "ui-tls-client-ca", PathBuf);
+    let (certificate_path, key_path, generate) = match (
+        value_m!(multi_config, "ui-tls-certificate", PathBuf),
+        value_m!(multi_config, "ui-tls-key", PathBuf),
+    ) {
+        (Some(certificate_path), Some(key_path)) => (certificate_path, key_path, false),
+        _ => (
+            default_directory.join(UI_TLS_CERTIFICATE_FILE),
+            default_directory.join(UI_TLS_KEY_FILE),
+            true,
+        ),
+    };
+    Some(UiTlsConfig {
+        certificate_path,
+        key_path,
+        generate,
+        interfaces,
+        allowlist,
+        client_ca_path_opt,
+    })
+}
+
+pub fn ui_auth_config_from(multi_config: &MultiConfig) -> Option<UiAuthConfig> {
+    let non_empty = |name: &str| value_m!(multi_config, name, String).filter(|s| !s.is_empty());
+    non_empty("ui-password").map(|full_password| UiAuthConfig {
+        full_password,
+        read_only_password_opt: non_empty("ui-read-only-password"),
+    })
+}
+
 pub fn port_is_busy(port: u16) -> bool {
     TcpListener::bind(SocketAddr::new(localhost(), port)).is_err()
 }
@@ -639,14 +777,14 @@ pub trait WalletCreationConfigMaker {
         &self,
         multi_config: &MultiConfig,
         streams: &mut StdStreams<'_>,
-    ) -> WalletCreationConfig {
+    ) -> Result<WalletCreationConfig, ConfiguratorError> {
         let mnemonic_passphrase = match value_m!(multi_config, "mnemonic-passphrase", String) {
             Some(mp) => mp,
-            None => self.make_mnemonic_passphrase(multi_config, streams),
+            None => self.make_mnemonic_passphrase(multi_config, streams)?,
         };
         let db_password = match value_m!(multi_config, "db-password", String) {
             Some(wp) => wp,
-            None => self.make_db_password(streams),
+            None => self.make_db_password(multi_config, streams)?,
         };
         let consuming_derivation_path = match value_m!(multi_config, "consuming-wallet", String) {
             Some(cdp) => cdp,
@@ -671,19 +809,21 @@ pub trait WalletCreationConfigMaker {
             &mnemonic_passphrase,
             &consuming_derivation_path,
             &earning_wallet_info,
-        );
+        )?;
+        derive_wallet(
+            &mnemonic_seed,
+            &consuming_derivation_path,
+            "consuming-wallet",
+        )?;
         let real_user = match value_m!(multi_config, "real-user", RealUser) {
             Some(ru) => ru,
             None => RealUser::null(),
         };
-        WalletCreationConfig {
+        Ok(WalletCreationConfig {
             earning_wallet_address_opt: match &earning_wallet_info {
                 Either::Left(address) => Some(address.clone()),
                 Either::Right(path) => {
-                    let keypair = Bip32ECKeyPair::from_raw(mnemonic_seed.as_slice(), path)
-                        .expect("--earning-wallet not properly validated by clap");
-                    let wallet = Wallet::from(keypair);
-                    Some(wallet.to_string())
+                    Some(derive_wallet(&mnemonic_seed, path, "earning-wallet")?.to_string())
                 }
             },
             derivation_path_info_opt: Some(DerivationPathWalletInfo {
@@ -692,18 +832,23 @@ pub trait WalletCreationConfigMaker {
                 consuming_derivation_path_opt: Some(consuming_derivation_path),
             }),
             real_user,
-        }
+        })
     }
 
-    fn make_db_password(&self, streams: &mut StdStreams) -> String {
+    fn make_db_password(
+        &self,
+        multi_config: &MultiConfig,
+        streams: &mut StdStreams,
+    ) -> Result<String, ConfiguratorError> {
         match request_new_db_password(
             streams,
+            prompt_backend(multi_config).as_ref(),
             Some("\n\nPlease provide a password to encrypt your wallet (This password can be changed later)..."),
             "  Enter password: ",
             "  Confirm password: ",
         ) {
-            Some(wp) => wp,
-            None => panic!("Wallet encryption password is required!")
+            Some(wp) => Ok(wp),
+            None => Err(ConfiguratorError::required("db-password", "Wallet encryption password is required")),
         }
     }
 
@@ -719,7 +864,7 @@ pub trait WalletCreationConfigMaker {
         &self,
         multi_config: &MultiConfig,
         streams: &mut StdStreams<'_>,
-    ) -> String;
+    ) -> Result<String, ConfiguratorError>;
 
     fn make_mnemonic_seed(
         &self,
@@ -728,7 +873,7 @@ pub trait WalletCreationConfigMaker {
         mnemonic_passphrase: &str,
         consuming_derivation_path: &str,
         earning_wallet_info: &Either<String, String>,
-    ) -> PlainData;
+    ) -> Result<PlainData, ConfiguratorError>;
 }
 
 #[cfg(test)]
@@ -737,6 +882,7 @@ mod tests {
     use crate::blockchain::bip32::Bip32ECKeyPair;
     use crate::db_config::persistent_configuration::PersistentConfigError;
     use crate::node_configurator::node_configurator_standard::app;
+    use crate::node_configurator::prompt_backend::TtyPromptBackend;
     use crate::node_test_utils::MockDirsWrapper;
     use crate::sub_lib::utils::make_new_test_multi_config;
     use crate::sub_lib::wallet::{Wallet, DEFAULT_EARNING_DERIVATION_PATH};
@@ -745,10 +891,12 @@ mod tests {
     use bip39::{Mnemonic, MnemonicType, Seed};
     use masq_lib::constants::DEFAULT_CHAIN_NAME;
     use masq_lib::multi_config::MultiConfig;
-    use masq_lib::shared_schema::{db_password_arg, ParamError};
+    use masq_lib::shared_schema::{db_password_arg, ParamError, CONFIGURATION_FAILURE_EXIT_CODE};
     use masq_lib::test_utils::environment_guard::EnvironmentGuard;
     use masq_lib::test_utils::fake_stream_holder::{ByteArrayWriter, FakeStreamHolder};
-    use masq_lib::test_utils::utils::TEST_DEFAULT_CHAIN_NAME;
+    use masq_lib::test_utils::utils::{
+        ensure_node_home_directory_exists, DEFAULT_CHAIN_ID, TEST_DEFAULT_CHAIN_NAME,
+    };
     use masq_lib::utils::{find_free_port, running_test};
     use std::io::Cursor;
     use std::net::{SocketAddr, TcpListener};
@@ -1014,7 +1162,8 @@ mod tests {
             &real_user,
             &data_directory_opt,
             &chain_name,
-        );
+        )
+        .unwrap();
 
         let expected_root = RealDirsWrapper {}.data_dir().unwrap();
         let expected_directory = expected_root.join("MASQ").join(DEFAULT_CHAIN_NAME);
@@ -1022,6 +1171,48 @@ mod tests {
         assert_eq!(&chain_name, DEFAULT_CHAIN_NAME);
     }
 
+    #[test]
+    fn data_directory_from_context_without_a_real_user_home_directory_is_an_error() {
+        let real_user = RealUser::new(Some(123), Some(456), None);
+
+        let result = data_directory_from_context(
+            &MockDirsWrapper::new().data_dir_result(Some("mocked/path".into())),
+            &real_user,
+            &None,
+            DEFAULT_CHAIN_NAME,
+        );
+
+        assert_eq!(
+            result,
+            Err(ConfiguratorError::required(
+                "real-user",
+                "No real-user home directory; specify --real-user"
+            ))
+        );
+    }
+
+    #[test]
+    fn initialize_database_reports_a_database_that_cannot_be_opened_as_an_error() {
+        let data_directory = ensure_node_home_directory_exists(
+            "node_configurator",
+            "initialize_database_reports_a_database_that_cannot_be_opened_as_an_error",
+        );
+        std::fs::create_dir_all(data_directory.join(DATABASE_FILE)).unwrap();
+
+        let result = initialize_database(&data_directory, DEFAULT_CHAIN_ID);
+
+        let param_errors = result.err().unwrap().param_errors;
+        assert_eq!(param_errors.len(), 1);
+        assert_eq!(param_errors[0].parameter, "data-directory");
+        assert!(
+            param_errors[0]
+                .reason
+                .starts_with("Can't initialize database at "),
+            "{}",
+            param_errors[0].reason
+        );
+    }
+
     #[test]
     fn determine_config_file_path_finds_path_in_args() {
         let _guard = EnvironmentGuard::new();
@@ -1193,15 +1384,16 @@ mod tests {
             stdout: stdout_writer,
             stderr: &mut ByteArrayWriter::new(),
         };
-        let persistent_configuration = PersistentConfigurationMock::new()
+        let mut persistent_configuration = PersistentConfigurationMock::new()
             .check_password_result(Ok(false))
             .check_password_result(Ok(true));
 
         let actual = request_existing_db_password(
             streams,
+            &TtyPromptBackend {},
             Some("Decrypt wallet"),
             "Enter password: ",
-            &persistent_configuration,
+            &mut persistent_configuration,
         );
 
         assert_eq!(actual, Ok(Some("Too Many S3cr3ts!".to_string())));
@@ -1221,15 +1413,16 @@ mod tests {
             stdout: stdout_writer,
             stderr: &mut ByteArrayWriter::new(),
         };
-        let persistent_configuration = PersistentConfigurationMock::new()
+        let mut persistent_configuration = PersistentConfigurationMock::new()
             .check_password_result(Ok(false))
             .check_password_result(Ok(true));
 
         let actual = request_existing_db_password(
             streams,
+            &TtyPromptBackend {},
             Some("Decrypt wallet"),
             "Enter password: ",
-            &persistent_configuration,
+            &mut persistent_configuration,
         );
 
         assert_eq!(actual, Ok(Some("booga".to_string())));
@@ -1246,11 +1439,16 @@ mod tests {
     #[test]
     fn request_existing_db_password_handles_error_checking_for_no_password() {
         let mut holder = FakeStreamHolder::new();
-        let persistent_config = PersistentConfigurationMock::new()
+        let mut persistent_config = PersistentConfigurationMock::new()
             .check_password_result(Err(PersistentConfigError::NotPresent));
 
-        let result =
-            request_existing_db_password(&mut holder.streams(), None, "prompt", &persistent_config);
+        let result = request_existing_db_password(
+            &mut holder.streams(),
+            &TtyPromptBackend {},
+            None,
+            "prompt",
+            &mut persistent_config,
+        );
 
         assert_eq!(
             result,
@@ -1266,11 +1464,17 @@ mod tests {
             stdout: stdout_writer,
             stderr: &mut ByteArrayWriter::new(),
         };
-        let persistent_config = PersistentConfigurationMock::new()
+        let mut persistent_config = PersistentConfigurationMock::new()
             .check_password_result(Ok(false))
             .check_password_result(Err(PersistentConfigError::NotPresent));
 
-        let result = request_existing_db_password(&mut streams, None, "prompt", &persistent_config);
+        let result = request_existing_db_password(
+            &mut streams,
+            &TtyPromptBackend {},
+            None,
+            "prompt",
+            &mut persistent_config,
+        );
 
         assert_eq!(
             result,
@@ -1289,7 +1493,7 @@ mod tests {
             stderr: &mut ByteArrayWriter::new(),
         };
         let check_password_params_arc = Arc::new(Mutex::new(vec![]));
-        let persistent_configuration = PersistentConfigurationMock::new()
+        let mut persistent_configuration = PersistentConfigurationMock::new()
             .check_password_params(&check_password_params_arc)
             .check_password_result(Ok(false))
             .check_password_result(Ok(false))
@@ -1298,9 +1502,10 @@ mod tests {
 
         let actual = request_existing_db_password(
             streams,
+            &TtyPromptBackend {},
             Some("Decrypt wallet"),
             "Enter password: ",
-            &persistent_configuration,
+            &mut persistent_configuration,
         );
 
         assert_eq!(actual, Ok(None));
@@ -1327,6 +1532,52 @@ mod tests {
         )
     }
 
+    #[test]
+    fn request_database_password_counts_failures_and_gives_up_during_a_cooldown() {
+        let stdout_writer = &mut ByteArrayWriter::new();
+        let streams = &mut StdStreams {
+            stdin: &mut Cursor::new(&b"bad password\nanother bad password\n"[..]),
+            stdout: stdout_writer,
+            stderr: &mut ByteArrayWriter::new(),
+        };
+        let now = SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let set_db_password_failures_params_arc = Arc::new(Mutex::new(vec![]));
+        let mut persistent_configuration = PersistentConfigurationMock::new()
+            .check_password_result(Ok(false))
+            .check_password_result(Ok(false))
+            .db_password_failures_result(Ok((3, 0)))
+            .db_password_failures_result(Ok((4, now + 1000)))
+            .set_db_password_failures_params(&set_db_password_failures_params_arc);
+
+        let result = request_existing_db_password(
+            streams,
+            &TtyPromptBackend {},
+            None,
+            "Enter password: ",
+            &mut persistent_configuration,
+        );
+
+        let param_error = &result.unwrap_err().param_errors[0];
+        assert_eq!(param_error.parameter, "db-password".to_string());
+        assert!(param_error
+            .reason
+            .starts_with("Too many incorrect passwords; try again in "));
+        assert_eq!(
+            stdout_writer.get_string(),
+            "Enter password: \
+             Incorrect password. Try again.\n\
+             Enter password: "
+                .to_string()
+        );
+        let set_db_password_failures_params = set_db_password_failures_params_arc.lock().unwrap();
+        assert_eq!(set_db_password_failures_params.len(), 1);
+        assert_eq!(set_db_password_failures_params[0].0, 4);
+        assert!(set_db_password_failures_params[0].1 >= now + 30);
+    }
+
     #[test]
     fn request_database_password_aborts_before_prompting_if_database_has_no_password() {
         let stdout_writer = &mut ByteArrayWriter::new();
@@ -1335,14 +1586,15 @@ mod tests {
             stdout: stdout_writer,
             stderr: &mut ByteArrayWriter::new(),
         };
-        let persistent_configuration =
+        let mut persistent_configuration =
             PersistentConfigurationMock::new().check_password_result(Ok(true));
 
         let actual = request_existing_db_password(
             streams,
+            &TtyPromptBackend {},
             Some("Decrypt wallet"),
             "Enter password: ",
-            &persistent_configuration,
+            &mut persistent_configuration,
         );
 
         assert_eq!(actual, Ok(None));
@@ -1362,6 +1614,7 @@ mod tests {
 
         let actual = request_new_db_password(
             streams,
+            &TtyPromptBackend {},
             Some("\n\nPlease provide a password to encrypt your wallet (This password can be changed \
              later)..."), "  Enter password: ", "Confirm password: ",
         );
@@ -1390,6 +1643,7 @@ mod tests {
 
         let actual = request_new_db_password(
             streams,
+            &TtyPromptBackend {},
             Some("\n\nPlease provide a password to encrypt your wallet (This password can be changed \
              later)..."), "  Enter password: ", "\nConfirm password: ",
         );
@@ -1420,6 +1674,7 @@ mod tests {
 
         let actual = request_new_db_password(
             streams,
+            &TtyPromptBackend {},
             Some("\n\nPlease provide a password to encrypt your wallet (This password can be changed \
              later)..."), "  Enter password: ", "Confirm password: ",
         );
@@ -1451,9 +1706,9 @@ mod tests {
             &self,
             _multi_config: &MultiConfig,
             streams: &mut StdStreams,
-        ) -> String {
+        ) -> Result<String, ConfiguratorError> {
             flushed_write(streams.stdout, "Enter mnemonic passphrase: ");
-            "mnemonic passphrase".to_string()
+            Ok("mnemonic passphrase".to_string())
         }
 
         fn make_mnemonic_seed(
@@ -1463,8 +1718,8 @@ mod tests {
             _mnemonic_passphrase: &str,
             _consuming_derivation_path: &str,
             _earning_wallet_info: &Either<String, String>,
-        ) -> PlainData {
-            Self::hardcoded_mnemonic_seed()
+        ) -> Result<PlainData, ConfiguratorError> {
+            Ok(Self::hardcoded_mnemonic_seed())
         }
     }
 
@@ -1504,7 +1759,9 @@ mod tests {
             stderr: &mut ByteArrayWriter::new(),
         };
 
-        let config = subject.make_wallet_creation_config(&multi_config, &mut streams);
+        let config = subject
+            .make_wallet_creation_config(&multi_config, &mut streams)
+            .unwrap();
 
         let captured_output = stdout_writer.get_string();
         let expected_output = "Enter mnemonic passphrase: \
@@ -1554,7 +1811,9 @@ mod tests {
             stderr: &mut ByteArrayWriter::new(),
         };
 
-        let config = subject.make_wallet_creation_config(&multi_config, &mut streams);
+        let config = subject
+            .make_wallet_creation_config(&multi_config, &mut streams)
+            .unwrap();
 
         let captured_output = stdout_writer.get_string();
         let expected_output = "";
@@ -1602,7 +1861,9 @@ mod tests {
             stderr: &mut ByteArrayWriter::new(),
         };
 
-        let config = subject.make_wallet_creation_config(&multi_config, &mut streams);
+        let config = subject
+            .make_wallet_creation_config(&multi_config, &mut streams)
+            .unwrap();
 
         let captured_output = stdout_writer.get_string();
         let expected_output = "";
@@ -1624,8 +1885,7 @@ mod tests {
     }
 
     #[test]
-    #[should_panic(expected = "Wallet encryption password is required!")]
-    fn make_wallet_creation_config_panics_after_three_password_mismatches() {
+    fn make_wallet_creation_config_requires_a_password_after_three_password_mismatches() {
         running_test();
         let subject = TameWalletCreationConfigMaker::new();
         let streams = &mut StdStreams {
@@ -1636,7 +1896,36 @@ mod tests {
         let vcl = Box::new(CommandLineVcl::new(vec!["test".to_string()]));
         let multi_config = make_new_test_multi_config(&subject.app, vec![vcl]).unwrap();
 
-        subject.make_wallet_creation_config(&multi_config, streams);
+        let result = subject.make_wallet_creation_config(&multi_config, streams);
+
+        assert_eq!(
+            result,
+            Err(ConfiguratorError::required(
+                "db-password",
+                "Wallet encryption password is required"
+            ))
+        );
+    }
+
+    #[test]
+    fn derive_wallet_fails_without_panicking_on_a_path_that_derives_no_key() {
+        let seed = TameWalletCreationConfigMaker::hardcoded_mnemonic_seed();
+
+        let good = derive_wallet(&seed, DEFAULT_EARNING_DERIVATION_PATH, "earning-wallet");
+        let bad = derive_wallet(&seed, "booga", "earning-wallet");
+
+        assert_eq!(
+            good,
+            Ok(Wallet::from(
+                Bip32ECKeyPair::from_raw(seed.as_slice(), DEFAULT_EARNING_DERIVATION_PATH).unwrap()
+            ))
+        );
+        let bad = bad.unwrap_err();
+        assert_eq!(bad.exit_code(), CONFIGURATION_FAILURE_EXIT_CODE);
+        assert_eq!(bad.param_errors[0].parameter, "earning-wallet".to_string());
+        assert!(bad.param_errors[0]
+            .reason
+            .starts_with("Couldn't make key pair from derivation path 'booga'"));
     }
 
     #[test]
@@ -1,32 +1,50 @@
 // Copyright (c) 2017-2019, Substratum LLC (https://substratum.net) and/or its affiliates. All rights reserved.
 
-use crate::blockchain::bip32::Bip32ECKeyPair;
 use crate::blockchain::bip39::Bip39;
 use crate::db_config::persistent_configuration::PersistentConfiguration;
+use crate::node_configurator::prompt_backend::PromptBackend;
 use crate::node_configurator::{
     app_head, check_for_past_initialization, common_validators, consuming_wallet_arg,
-    create_wallet, earning_wallet_arg, flushed_write, language_arg, mnemonic_passphrase_arg,
-    prepare_initialization_mode, request_password_with_confirmation, request_password_with_retry,
+    create_wallet, derive_wallet, earning_wallet_arg, flushed_write, language_arg,
+    mnemonic_passphrase_arg, mnemonic_passphrase_error, prepare_initialization_mode,
+    prompt_backend, request_password_with_confirmation, request_password_with_retry,
     update_db_password, DirsWrapper, Either, NodeConfigurator, RealDirsWrapper,
     WalletCreationConfig, WalletCreationConfigMaker, DB_PASSWORD_HELP, EARNING_WALLET_HELP,
 };
+use crate::sub_lib::cli_output;
+use crate::sub_lib::cli_output::OutputFormat;
 use crate::sub_lib::cryptde::PlainData;
+use crate::sub_lib::deterministic;
 use crate::sub_lib::wallet::Wallet;
 use bip39::{Language, Mnemonic, MnemonicType};
 use clap::{value_t, App, Arg};
 use indoc::indoc;
+#[cfg(feature = "clipboard")]
+use masq_lib::clipboard::{
+    copy_for_a_while, wait_for_clipboard_clears, Clipboard, SystemClipboard, CLIPBOARD_TIMEOUT,
+};
 use masq_lib::command::StdStreams;
 use masq_lib::multi_config::MultiConfig;
 use masq_lib::shared_schema::{
-    chain_arg, data_directory_arg, db_password_arg, real_user_arg, ConfiguratorError,
+    chain_arg, data_directory_arg, db_password_arg, deterministic_seed_arg, output_arg,
+    password_prompt_arg, real_user_arg, ConfiguratorError,
 };
+use serde_json::json;
 use std::str::FromStr;
+#[cfg(feature = "clipboard")]
+use std::sync::Arc;
+#[cfg(feature = "clipboard")]
+use std::time::Duration;
 use unindent::unindent;
 
 pub struct NodeConfiguratorGenerateWallet {
     dirs_wrapper: Box<dyn DirsWrapper>,
     app: App<'static, 'static>,
     mnemonic_factory: Box<dyn MnemonicFactory>,
+    #[cfg(feature = "clipboard")]
+    clipboard: Arc<dyn Clipboard>,
+    #[cfg(feature = "clipboard")]
+    clipboard_timeout: Duration,
 }
 
 impl NodeConfigurator<WalletCreationConfig> for NodeConfiguratorGenerateWallet {
@@ -38,12 +56,15 @@ impl NodeConfigurator<WalletCreationConfig> for NodeConfiguratorGenerateWallet {
         let (multi_config, mut persistent_config_box) =
             prepare_initialization_mode(self.dirs_wrapper.as_ref(), &self.app, args, streams)?;
         check_for_past_initialization(persistent_config_box.as_ref())?;
+        deterministic::set_deterministic_seed(value_m!(multi_config, "deterministic-seed", u64));
         let persistent_config = persistent_config_box.as_mut();
 
         let config = self.parse_args(&multi_config, streams, persistent_config)?;
 
         update_db_password(&config, persistent_config)?;
         create_wallet(&config, persistent_config)?;
+        #[cfg(feature = "clipboard")]
+        self.copy_consuming_wallet_address(&multi_config, &config, streams)?;
 
         Ok(config)
     }
@@ -57,13 +78,21 @@ struct MnemonicFactoryReal {}
 
 impl MnemonicFactory for MnemonicFactoryReal {
     fn make(&self, mnemonic_type: MnemonicType, language: Language) -> Mnemonic {
-        Bip39::mnemonic(mnemonic_type, language)
+        match deterministic::deterministic_bytes_opt("mnemonic", mnemonic_type.entropy_bits() / 8) {
+            Some(entropy) => Mnemonic::from_entropy(&entropy, language)
+                .expect("Entropy of the wrong length for the mnemonic type"),
+            None => Bip39::mnemonic(mnemonic_type, language),
+        }
     }
 }
 
 const GENERATE_WALLET_HELP: &str =
     "Generate a new set of HD wallets with mnemonic recovery phrase from the standard \
      BIP39 predefined list of words. Not valid as an environment variable.";
+#[cfg(feature = "clipboard")]
+const COPY_TO_CLIPBOARD_HELP: &str =
+    "Put the consuming wallet's address on the clipboard, so that it needn't be typed in to fund the wallet, \
+     and clear it again after 30 seconds. The Node waits until then before it exits.";
 const WORD_COUNT_HELP: &str =
     "The number of words in the mnemonic phrase. Ropsten defaults to 12 words. \
      Mainnet defaults to 24 words.";
@@ -94,13 +123,14 @@ impl WalletCreationConfigMaker for NodeConfiguratorGenerateWallet {
         &self,
         multi_config: &MultiConfig,
         streams: &mut StdStreams,
-    ) -> String {
+    ) -> Result<String, ConfiguratorError> {
         match value_m!(multi_config, "mnemonic-passphrase", String) {
-            Some(mp) => mp,
-            None => match Self::request_mnemonic_passphrase(streams) {
-                Some(mp) => mp,
-                None => "".to_string(),
-            },
+            Some(mp) => Ok(mp),
+            None => Ok(Self::request_mnemonic_passphrase(
+                streams,
+                prompt_backend(multi_config).as_ref(),
+            )?
+            .unwrap_or_default()),
         }
     }
 
@@ -111,7 +141,7 @@ impl WalletCreationConfigMaker for NodeConfiguratorGenerateWallet {
         mnemonic_passphrase: &str,
         consuming_derivation_path: &str,
         earning_wallet_info: &Either<String, String>,
-    ) -> PlainData {
+    ) -> Result<PlainData, ConfiguratorError> {
         let language_str =
             value_m!(multi_config, "language", String).expect("--language is not defaulted");
         let language = Bip39::language_from_name(&language_str);
@@ -128,8 +158,12 @@ impl WalletCreationConfigMaker for NodeConfiguratorGenerateWallet {
             &consuming_derivation_path,
             &earning_wallet_info,
             multi_config.arg_matches().is_present("json"),
-        );
-        seed
+            match value_m!(multi_config, "output", String) {
+                Some(ref output) if output.eq_ignore_ascii_case("json") => OutputFormat::Json,
+                _ => OutputFormat::Human,
+            },
+        )?;
+        Ok(seed)
     }
 }
 
@@ -141,46 +175,103 @@ impl Default for NodeConfiguratorGenerateWallet {
 
 impl NodeConfiguratorGenerateWallet {
     pub fn new() -> Self {
+        let app = app_head()
+            .after_help(HELP_TEXT)
+            .arg(
+                Arg::with_name("generate-wallet")
+                    .long("generate-wallet")
+                    .required(true)
+                    .takes_value(false)
+                    .requires_all(&["language", "word-count"])
+                    .help(GENERATE_WALLET_HELP),
+            )
+            .arg(
+                Arg::with_name("json")
+                    .long("json")
+                    .takes_value(false)
+                    .hidden(true),
+            )
+            .arg(chain_arg())
+            .arg(consuming_wallet_arg())
+            .arg(data_directory_arg())
+            .arg(earning_wallet_arg(
+                EARNING_WALLET_HELP,
+                common_validators::validate_earning_wallet,
+            ))
+            .arg(language_arg())
+            .arg(mnemonic_passphrase_arg())
+            .arg(output_arg())
+            .arg(password_prompt_arg())
+            .arg(real_user_arg())
+            .arg(db_password_arg(DB_PASSWORD_HELP))
+            .arg(deterministic_seed_arg())
+            .arg(
+                Arg::with_name("word-count")
+                    .long("word-count")
+                    .required(true)
+                    .value_name("WORD-COUNT")
+                    .possible_values(&["12", "15", "18", "21", "24"])
+                    .default_value("12")
+                    .help(WORD_COUNT_HELP),
+            );
+        #[cfg(feature = "clipboard")]
+        let app = app.arg(
+            Arg::with_name("copy-to-clipboard")
+                .long("copy-to-clipboard")
+                .takes_value(false)
+                .help(COPY_TO_CLIPBOARD_HELP),
+        );
         Self {
             dirs_wrapper: Box::new(RealDirsWrapper {}),
-            app: app_head()
-                .after_help(HELP_TEXT)
-                .arg(
-                    Arg::with_name("generate-wallet")
-                        .long("generate-wallet")
-                        .required(true)
-                        .takes_value(false)
-                        .requires_all(&["language", "word-count"])
-                        .help(GENERATE_WALLET_HELP),
-                )
-                .arg(
-                    Arg::with_name("json")
-                        .long("json")
-                        .takes_value(false)
-                        .hidden(true),
-                )
-                .arg(chain_arg())
-                .arg(consuming_wallet_arg())
-                .arg(data_directory_arg())
-                .arg(earning_wallet_arg(
-                    EARNING_WALLET_HELP,
-                    common_validators::validate_earning_wallet,
-                ))
-                .arg(language_arg())
-                .arg(mnemonic_passphrase_arg())
-                .arg(real_user_arg())
-                .arg(db_password_arg(DB_PASSWORD_HELP))
-                .arg(
-                    Arg::with_name("word-count")
-                        .long("word-count")
-                        .required(true)
-                        .value_name("WORD-COUNT")
-                        .possible_values(&["12", "15", "18", "21", "24"])
-                        .default_value("12")
-                        .help(WORD_COUNT_HELP),
-                ),
+            app,
             mnemonic_factory: Box::new(MnemonicFactoryReal {}),
+            #[cfg(feature = "clipboard")]
+            clipboard: Arc::new(SystemClipboard {}),
+            #[cfg(feature = "clipboard")]
+            clipboard_timeout: CLIPBOARD_TIMEOUT,
+        }
+    }
+
+    // A clipboard that doesn't work is no reason to fail: the address has been written out anyway
+    #[cfg(feature = "clipboard")]
+    fn copy_consuming_wallet_address(
+        &self,
+        multi_config: &MultiConfig,
+        config: &WalletCreationConfig,
+        streams: &mut StdStreams<'_>,
+    ) -> Result<(), ConfiguratorError> {
+        if !multi_config.arg_matches().is_present("copy-to-clipboard") {
+            return Ok(());
+        }
+        let info = config
+            .derivation_path_info_opt
+            .as_ref()
+            .expect("Generated wallets have no seed");
+        let derivation_path = info
+            .consuming_derivation_path_opt
+            .as_ref()
+            .expect("Generated wallets have no consuming derivation path");
+        let address =
+            derive_wallet(&info.mnemonic_seed, derivation_path, "consuming-wallet")?.to_string();
+        match copy_for_a_while(self.clipboard.clone(), &address, self.clipboard_timeout) {
+            Ok(()) => {
+                writeln!(
+                    streams.stderr,
+                    "Copied the consuming wallet address {} to the clipboard; clearing it in {} seconds...",
+                    address,
+                    self.clipboard_timeout.as_secs()
+                )
+                .expect("writeln! failed");
+                wait_for_clipboard_clears();
+            }
+            Err(e) => writeln!(
+                streams.stderr,
+                "Couldn't copy the consuming wallet address to the clipboard: {}",
+                e
+            )
+            .expect("writeln! failed"),
         }
+        Ok(())
     }
 
     fn parse_args(
@@ -190,32 +281,39 @@ impl NodeConfiguratorGenerateWallet {
         persistent_config: &dyn PersistentConfiguration,
     ) -> Result<WalletCreationConfig, ConfiguratorError> {
         match persistent_config.mnemonic_seed_exists() {
-            Ok(true) => panic!("Can't generate wallets: mnemonic seed has already been created"),
+            Ok(true) => {
+                return Err(ConfiguratorError::failed(
+                    "generate-wallet",
+                    "Can't generate wallets: mnemonic seed has already been created",
+                ))
+            }
             Ok(false) => (),
             Err(pce) => return Err(pce.into_configurator_error("seed")),
         }
-        Ok(self.make_wallet_creation_config(multi_config, streams))
+        self.make_wallet_creation_config(multi_config, streams)
     }
 
-    fn request_mnemonic_passphrase(streams: &mut StdStreams) -> Option<String> {
+    fn request_mnemonic_passphrase(
+        streams: &mut StdStreams,
+        backend: &dyn PromptBackend,
+    ) -> Result<Option<String>, ConfiguratorError> {
         flushed_write(
             streams.stdout,
             "\nPlease provide an extra mnemonic passphrase to ensure your wallet is unique\n\
             (NOTE: This passphrase cannot be changed later and still produce the same addresses).\n\
             You will encrypt your wallet in a following step...\n",
         );
-        match request_password_with_retry(
-            "  Mnemonic passphrase (recommended): ",
-            streams,
-            |streams| {
-                request_password_with_confirmation(
-                    "  Confirm mnemonic passphrase: ",
-                    "\nPassphrases do not match.",
-                    streams,
-                    |_| Ok(()),
-                )
-            },
-        ) {
+        match request_password_with_retry(streams, backend, |streams| {
+            request_password_with_confirmation(
+                backend,
+                "mnemonic-passphrase",
+                "  Mnemonic passphrase (recommended): ",
+                "  Confirm mnemonic passphrase: ",
+                "\nPassphrases do not match.",
+                streams,
+                |_| Ok(()),
+            )
+        }) {
             Ok(mp) => {
                 if mp.is_empty() {
                     flushed_write (
@@ -223,12 +321,12 @@ impl NodeConfiguratorGenerateWallet {
                         "\nWhile ill-advised, proceeding with no mnemonic passphrase.\nPress Enter to continue...",
                     );
                     let _ = streams.stdin.read(&mut [0u8]).is_ok();
-                    None
+                    Ok(None)
                 } else {
-                    Some(mp)
+                    Ok(Some(mp))
                 }
             }
-            Err(e) => panic!("{:?}", e),
+            Err(e) => Err(mnemonic_passphrase_error(e)),
         }
     }
 
@@ -239,39 +337,50 @@ impl NodeConfiguratorGenerateWallet {
         consuming_derivation_path: &str,
         earning_wallet_info: &Either<String, String>,
         json: bool,
-    ) {
-        let consuming_keypair = Bip32ECKeyPair::from_raw(seed.as_ref(), &consuming_derivation_path)
-            .unwrap_or_else(|_| {
-                panic!(
-                    "Couldn't make key pair from consuming derivation path '{}'",
-                    consuming_derivation_path
-                )
-            });
-        let consuming_wallet = Wallet::from(consuming_keypair);
+        output_format: OutputFormat,
+    ) -> Result<(), ConfiguratorError> {
+        let consuming_wallet = derive_wallet(seed, consuming_derivation_path, "consuming-wallet")?;
+        let earning_wallet = match earning_wallet_info {
+            Either::Left(address) => {
+                Wallet::from_str(address).expect("Address doesn't work anymore")
+            }
+            Either::Right(earning_derivation_path) => {
+                derive_wallet(seed, earning_derivation_path, "earning-wallet")?
+            }
+        };
 
-        if json {
+        if output_format == OutputFormat::Json {
+            let earning_wallet = match &earning_wallet_info {
+                Either::Left(_) => json!({
+                    "address": earning_wallet.to_string(),
+                }),
+                Either::Right(earning_derivation_path) => json!({
+                    "derivationPath": earning_derivation_path,
+                    "address": earning_wallet.to_string(),
+                }),
+            };
+            cli_output::write(
+                streams.stdout,
+                &cli_output::success(
+                    "generateWallet",
+                    json!({
+                        "mnemonicPhrase": mnemonic.phrase(),
+                        "consumingWallet": {
+                            "derivationPath": consuming_derivation_path,
+                            "address": consuming_wallet.to_string(),
+                        },
+                        "earningWallet": earning_wallet,
+                    }),
+                ),
+            );
+        } else if json {
             let earning_wallet_object_body = match &earning_wallet_info {
-                Either::Left(address) => {
-                    let earning_wallet =
-                        Wallet::from_str(address).expect("Address doesn't work anymore");
-                    format!(r#""address": "{}""#, earning_wallet)
-                }
-                Either::Right(earning_derivation_path) => {
-                    let earning_keypair =
-                        Bip32ECKeyPair::from_raw(seed.as_ref(), &earning_derivation_path)
-                            .unwrap_or_else(|_| {
-                                panic!(
-                                    "Couldn't make key pair from earning derivation path '{}'",
-                                    earning_derivation_path
-                                )
-                            });
-                    let earning_wallet = Wallet::from(earning_keypair.address());
-                    format!(
-                        r#""derivationPath": "{}",
+                Either::Left(_) => format!(r#""address": "{}""#, earning_wallet),
+                Either::Right(earning_derivation_path) => format!(
+                    r#""derivationPath": "{}",
                         "address": "{}""#,
-                        earning_derivation_path, earning_wallet
-                    )
-                }
+                    earning_derivation_path, earning_wallet
+                ),
             };
             let result = unindent(&format!(
                 r#"
@@ -310,24 +419,13 @@ impl NodeConfiguratorGenerateWallet {
                 ),
             );
             match &earning_wallet_info {
-                Either::Left(address) => {
-                    let earning_wallet =
-                        Wallet::from_str(address).expect("Address doesn't work anymore");
+                Either::Left(_) => {
                     flushed_write(
                         streams.stdout,
                         &format!("  Earning Wallet: {}\n", earning_wallet),
                     );
                 }
                 Either::Right(earning_derivation_path) => {
-                    let earning_keypair =
-                        Bip32ECKeyPair::from_raw(seed.as_ref(), &earning_derivation_path)
-                            .unwrap_or_else(|_| {
-                                panic!(
-                                    "Couldn't make key pair from earning derivation path '{}'",
-                                    earning_derivation_path
-                                )
-                            });
-                    let earning_wallet = Wallet::from(earning_keypair.address());
                     flushed_write(
                         streams.stdout,
                         &format!(
@@ -338,12 +436,14 @@ impl NodeConfiguratorGenerateWallet {
                 }
             };
         }
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::blockchain::bip32::Bip32ECKeyPair;
     use crate::bootstrapper::RealUser;
     use crate::database::db_initializer;
     use crate::database::db_initializer::DbInitializer;
@@ -407,6 +507,86 @@ mod tests {
         }
     }
 
+    #[cfg(feature = "clipboard")]
+    #[test]
+    fn the_consuming_wallet_address_is_copied_to_the_clipboard_and_cleared_only_when_asked() {
+        use masq_lib::test_utils::clipboard_mock::ClipboardMock;
+        let mnemonic = Mnemonic::new(MnemonicType::Words12, Language::English);
+        let seed = PlainData::new(Seed::new(&mnemonic, "").as_ref());
+        let config = WalletCreationConfig {
+            earning_wallet_address_opt: None,
+            derivation_path_info_opt: Some(DerivationPathWalletInfo {
+                mnemonic_seed: seed.clone(),
+                db_password: "password".to_string(),
+                consuming_derivation_path_opt: Some(DEFAULT_CONSUMING_DERIVATION_PATH.to_string()),
+            }),
+            real_user: RealUser::null(),
+        };
+        let clipboard = Arc::new(ClipboardMock::default());
+        let mut subject = NodeConfiguratorGenerateWallet::new();
+        subject.clipboard = clipboard.clone();
+        subject.clipboard_timeout = Duration::from_millis(10);
+        let multi_config = |args: ArgsBuilder| {
+            make_new_test_multi_config(
+                &subject.app,
+                vec![Box::new(CommandLineVcl::new(args.into()))],
+            )
+            .unwrap()
+        };
+        let mut not_asked = FakeStreamHolder::new();
+        let mut asked = FakeStreamHolder::new();
+
+        subject
+            .copy_consuming_wallet_address(
+                &multi_config(ArgsBuilder::new().opt("--generate-wallet")),
+                &config,
+                &mut not_asked.streams(),
+            )
+            .unwrap();
+        subject
+            .copy_consuming_wallet_address(
+                &multi_config(
+                    ArgsBuilder::new()
+                        .opt("--generate-wallet")
+                        .opt("--copy-to-clipboard"),
+                ),
+                &config,
+                &mut asked.streams(),
+            )
+            .unwrap();
+
+        let address = derive_wallet(&seed, DEFAULT_CONSUMING_DERIVATION_PATH, "consuming-wallet")
+            .unwrap()
+            .to_string();
+        assert_eq!(not_asked.stderr.get_string(), "");
+        assert_eq!(
+            asked.stderr.get_string(),
+            format!(
+                "Copied the consuming wallet address {} to the clipboard; clearing it in 0 seconds...\n",
+                address
+            )
+        );
+        assert_eq!(
+            *clipboard.contents.lock().unwrap(),
+            vec![address, "".to_string()]
+        );
+    }
+
+    #[test]
+    fn mnemonic_factory_real_makes_the_same_mnemonic_for_the_same_deterministic_seed() {
+        let subject = MnemonicFactoryReal {};
+        deterministic::set_deterministic_seed(Some(1234));
+        let first = subject.make(MnemonicType::Words24, Language::English);
+        deterministic::set_deterministic_seed(Some(1234));
+        let second = subject.make(MnemonicType::Words24, Language::English);
+        deterministic::set_deterministic_seed(None);
+        let third = subject.make(MnemonicType::Words24, Language::English);
+
+        assert_eq!(first.phrase(), second.phrase());
+        assert_eq!(first.phrase().split(' ').count(), 24);
+        assert_ne!(first.phrase(), third.phrase());
+    }
+
     #[test]
     fn report_wallet_information_can_output_json_with_an_earning_derivation_path() {
         let mut streams = FakeStreamHolder::new();
@@ -420,7 +600,9 @@ mod tests {
             "m/44'/60'/0'/0/0",
             &Either::Right("m/44'/60'/0'/0/1".to_string()),
             true,
-        );
+            OutputFormat::Human,
+        )
+        .unwrap();
 
         let result = streams.stdout.get_string();
         println!("{}", result);
@@ -444,7 +626,9 @@ mod tests {
             "m/44'/60'/0'/0/0",
             &Either::Left("0x01234567890ABCDEFabcdef01234567890ABCDEF".to_string()),
             true,
-        );
+            OutputFormat::Human,
+        )
+        .unwrap();
 
         let result = streams.stdout.get_string();
         println!("{}", result);
@@ -459,6 +643,50 @@ mod tests {
         );
     }
 
+    #[test]
+    fn report_wallet_information_can_wrap_the_wallets_for_json_output() {
+        let mut streams = FakeStreamHolder::new();
+        let mnemonic = Mnemonic::new(MnemonicType::Words12, Language::English);
+        let seed = Seed::new(&mnemonic, "Mortimer");
+
+        NodeConfiguratorGenerateWallet::report_wallet_information(
+            &mut streams.streams(),
+            &mnemonic,
+            &PlainData::new(seed.as_bytes()),
+            "m/44'/60'/0'/0/0",
+            &Either::Right("m/44'/60'/0'/0/1".to_string()),
+            false,
+            OutputFormat::Json,
+        )
+        .unwrap();
+
+        let result: serde_json::Value = serde_json::from_str(&streams.stdout.get_string()).unwrap();
+        assert_eq!(result["version"], json!(1));
+        assert_eq!(result["mode"], json!("generateWallet"));
+        assert_eq!(result["result"]["mnemonicPhrase"], json!(mnemonic.phrase()));
+        let consuming_wallet =
+            Wallet::from(Bip32ECKeyPair::from_raw(seed.as_bytes(), "m/44'/60'/0'/0/0").unwrap());
+        assert_eq!(
+            result["result"]["consumingWallet"],
+            json!({
+                "derivationPath": "m/44'/60'/0'/0/0",
+                "address": consuming_wallet.to_string(),
+            })
+        );
+        let earning_wallet = Wallet::from(
+            Bip32ECKeyPair::from_raw(seed.as_bytes(), "m/44'/60'/0'/0/1")
+                .unwrap()
+                .address(),
+        );
+        assert_eq!(
+            result["result"]["earningWallet"],
+            json!({
+                "derivationPath": "m/44'/60'/0'/0/1",
+                "address": earning_wallet.to_string(),
+            })
+        );
+    }
+
     #[test]
     fn exercise_configure() {
         let _clap_guard = ClapGuard::new();
@@ -493,7 +721,7 @@ mod tests {
             .configure(args_vec.as_slice(), &mut FakeStreamHolder::new().streams())
             .unwrap();
 
-        let persistent_config = initialize_database(&home_dir, DEFAULT_CHAIN_ID);
+        let persistent_config = initialize_database(&home_dir, DEFAULT_CHAIN_ID).unwrap();
         assert_eq!(persistent_config.check_password(Some(password)), Ok(true));
         let mut make_parameters = make_parameters_arc.lock().unwrap();
         assert_eq_debug(
@@ -613,7 +841,9 @@ mod tests {
         )
         .unwrap();
 
-        subject.make_mnemonic_passphrase(&multi_config, streams);
+        subject
+            .make_mnemonic_passphrase(&multi_config, streams)
+            .unwrap();
 
         let captured_output = stdout_writer.get_string();
         let expected_output = "\nPlease provide an extra mnemonic passphrase to ensure your wallet is unique\n\
@@ -625,8 +855,7 @@ mod tests {
     }
 
     #[test]
-    #[should_panic(expected = "RetriesExhausted")]
-    fn make_mnemonic_passphrase_panics_after_three_passphrase_mismatches() {
+    fn make_mnemonic_passphrase_gives_up_after_three_passphrase_mismatches() {
         let subject = NodeConfiguratorGenerateWallet::new();
         let streams = &mut StdStreams {
             stdin: &mut Cursor::new(&b"one\neno\ntwo\nowt\nthree\neerht\n"[..]),
@@ -640,7 +869,15 @@ mod tests {
         )
         .unwrap();
 
-        subject.make_mnemonic_passphrase(&multi_config, streams);
+        let result = subject.make_mnemonic_passphrase(&multi_config, streams);
+
+        assert_eq!(
+            result,
+            Err(ConfiguratorError::required(
+                "mnemonic-passphrase",
+                "Mnemonic passphrases did not match"
+            ))
+        );
     }
 
     #[test]
@@ -659,7 +896,9 @@ mod tests {
         let vcl = Box::new(CommandLineVcl::new(args.into()));
         let multi_config = make_new_test_multi_config(&subject.app, vec![vcl]).unwrap();
 
-        subject.make_mnemonic_passphrase(&multi_config, &mut streams);
+        subject
+            .make_mnemonic_passphrase(&multi_config, &mut streams)
+            .unwrap();
 
         let captured_output = stdout_writer.get_string();
         let expected_output = "\nPlease provide an extra mnemonic passphrase to ensure your wallet is unique\n\
@@ -671,11 +910,10 @@ mod tests {
     }
 
     #[test]
-    #[should_panic(expected = "Can't generate wallets: mnemonic seed has already been created")]
-    fn preexisting_mnemonic_seed_causes_collision_and_panics() {
+    fn preexisting_mnemonic_seed_causes_collision_and_fails() {
         let data_directory = ensure_node_home_directory_exists(
             "node_configurator_generate_wallet",
-            "preexisting_mnemonic_seed_causes_collision_and_panics",
+            "preexisting_mnemonic_seed_causes_collision_and_fails",
         );
 
         let conn = db_initializer::DbInitializerReal::new()
@@ -698,12 +936,18 @@ mod tests {
         let vcl = Box::new(CommandLineVcl::new(args.into()));
         let multi_config = make_new_test_multi_config(&subject.app, vec![vcl]).unwrap();
 
-        subject
-            .parse_args(
-                &multi_config,
-                &mut FakeStreamHolder::new().streams(),
-                &persistent_config,
-            )
-            .unwrap();
+        let result = subject.parse_args(
+            &multi_config,
+            &mut FakeStreamHolder::new().streams(),
+            &persistent_config,
+        );
+
+        assert_eq!(
+            result,
+            Err(ConfiguratorError::failed(
+                "generate-wallet",
+                "Can't generate wallets: mnemonic seed has already been created"
+            ))
+        );
     }
 }
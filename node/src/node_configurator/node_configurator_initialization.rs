@@ -1,13 +1,22 @@
 // Copyright (c) 2017-2019, Substratum LLC (https://substratum.net) and/or its affiliates. All rights reserved.
 
-use crate::node_configurator::{app_head, NodeConfigurator};
+use crate::daemon::restart_policy::{RestartPolicy, DEFAULT_RESTART_BACKOFF};
+use crate::node_configurator::{app_head, DirsWrapper, NodeConfigurator, RealDirsWrapper};
+use crate::sub_lib::ui_gateway::{UiAuthConfig, UiTlsConfig};
 use crate::sub_lib::utils::make_new_multi_config;
 use clap::{App, Arg};
 use lazy_static::lazy_static;
 use masq_lib::command::StdStreams;
 use masq_lib::constants::{HIGHEST_USABLE_PORT, LOWEST_USABLE_INSECURE_PORT};
 use masq_lib::multi_config::CommandLineVcl;
-use masq_lib::shared_schema::{ui_port_arg, ConfiguratorError};
+use masq_lib::shared_schema::common_validators::{
+    validate_restart_backoff, validate_restart_max_retries,
+};
+use masq_lib::shared_schema::{
+    ui_allowlist_arg, ui_grpc_port_arg, ui_interfaces_arg, ui_password_arg, ui_port_arg,
+    ui_read_only_password_arg, ui_tls_arg, ui_tls_certificate_arg, ui_tls_client_ca_arg,
+    ui_tls_key_arg, ConfiguratorError,
+};
 
 lazy_static! {
     static ref UI_PORT_HELP: String = format!(
@@ -16,11 +25,30 @@ lazy_static! {
         Best to accept the default unless you know what you're doing. Must be between {} and {}.",
         LOWEST_USABLE_INSECURE_PORT, HIGHEST_USABLE_PORT
     );
+    static ref RESTART_BACKOFF_HELP: String = format!(
+        "How many seconds the Daemon waits before its first attempt to restart a crashed Node. The wait \
+        doubles with each further attempt, up to ten minutes. Must be greater than zero. Default is {}.",
+        DEFAULT_RESTART_BACKOFF.as_secs()
+    );
 }
 
+const UI_GRPC_PORT_HELP: &str =
+    "The port on 127.0.0.1 where the Daemon should serve gRPC Setup calls, so that controllers can configure \
+     the Node with stubs generated from proto/masq_ui.proto instead of speaking WebSockets. The Node serves \
+     its own calls at its own --ui-grpc-port. If you don't specify a port, the Daemon has no gRPC endpoint.";
+
+const RESTART_MAX_RETRIES_HELP: &str =
+    "How many times in a row the Daemon will try to restart a Node that has crashed before it gives up. \
+     A Node that stays up for ten minutes earns all its retries back. Default is 0, which means a crashed \
+     Node stays down until a UI starts it again.";
+
 #[derive(Default, Clone, PartialEq, Debug)]
 pub struct InitializationConfig {
     pub ui_port: u16,
+    pub ui_tls_opt: Option<UiTlsConfig>,
+    pub ui_auth_opt: Option<UiAuthConfig>,
+    pub ui_grpc_port_opt: Option<u16>,
+    pub restart_policy: RestartPolicy,
 }
 
 pub struct NodeConfiguratorInitialization {}
@@ -38,7 +66,7 @@ impl NodeConfigurator<InitializationConfig> for NodeConfiguratorInitialization {
             streams,
         )?;
         let mut config = InitializationConfig::default();
-        initialization::parse_args(&multi_config, &mut config, streams);
+        initialization::parse_args(&multi_config, &mut config, &RealDirsWrapper {}, streams);
         Ok(config)
     }
 }
@@ -53,31 +81,78 @@ pub fn app() -> App<'static, 'static> {
                 .help("Directs MASQ to start the Daemon that controls the Node, rather than the Node itself"),
         )
         .arg(ui_port_arg(&UI_PORT_HELP))
+        .arg(ui_tls_arg())
+        .arg(ui_tls_certificate_arg())
+        .arg(ui_tls_key_arg())
+        .arg(ui_interfaces_arg())
+        .arg(ui_allowlist_arg())
+        .arg(ui_tls_client_ca_arg())
+        .arg(ui_password_arg())
+        .arg(ui_read_only_password_arg())
+        .arg(ui_grpc_port_arg(UI_GRPC_PORT_HELP))
+        .arg(
+            Arg::with_name("restart-max-retries")
+                .long("restart-max-retries")
+                .value_name("RETRIES")
+                .min_values(0)
+                .max_values(1)
+                .validator(validate_restart_max_retries)
+                .help(RESTART_MAX_RETRIES_HELP),
+        )
+        .arg(
+            Arg::with_name("restart-backoff")
+                .long("restart-backoff")
+                .value_name("SECONDS")
+                .min_values(0)
+                .max_values(1)
+                .validator(validate_restart_backoff)
+                .help(&RESTART_BACKOFF_HELP),
+        )
 }
 
 mod initialization {
     use super::*;
+    use crate::node_configurator::{ui_auth_config_from, ui_tls_config_from};
     use clap::value_t;
     use masq_lib::constants::DEFAULT_UI_PORT;
     use masq_lib::multi_config::MultiConfig;
+    use std::time::Duration;
 
     pub fn parse_args(
         multi_config: &MultiConfig,
         config: &mut InitializationConfig,
+        dirs_wrapper: &dyn DirsWrapper,
         _streams: &mut StdStreams<'_>,
     ) {
         config.ui_port = value_m!(multi_config, "ui-port", u16).unwrap_or(DEFAULT_UI_PORT);
+        // The Daemon isn't tied to any one chain, so its certificate lives above the chain directories
+        let default_tls_directory = dirs_wrapper.data_dir().unwrap_or_default().join("MASQ");
+        config.ui_tls_opt = ui_tls_config_from(multi_config, &default_tls_directory);
+        config.ui_auth_opt = ui_auth_config_from(multi_config);
+        config.ui_grpc_port_opt = value_m!(multi_config, "ui-grpc-port", u16);
+        config.restart_policy = RestartPolicy {
+            max_retries: value_m!(multi_config, "restart-max-retries", u32).unwrap_or(0),
+            backoff: value_m!(multi_config, "restart-backoff", u64)
+                .map(Duration::from_secs)
+                .unwrap_or(DEFAULT_RESTART_BACKOFF),
+        };
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::node_test_utils::MockDirsWrapper;
     use crate::sub_lib::utils::make_new_test_multi_config;
     use crate::test_utils::ArgsBuilder;
     use masq_lib::constants::DEFAULT_UI_PORT;
+    use masq_lib::ip_network::IpNetwork;
     use masq_lib::multi_config::{CommandLineVcl, VirtualCommandLine};
     use masq_lib::test_utils::fake_stream_holder::FakeStreamHolder;
+    use std::net::IpAddr;
+    use std::path::PathBuf;
+    use std::str::FromStr;
+    use std::time::Duration;
 
     #[test]
     fn parse_args_creates_configuration_with_defaults() {
@@ -90,17 +165,25 @@ mod tests {
         initialization::parse_args(
             &multi_config,
             &mut config,
+            &MockDirsWrapper::new().data_dir_result(Some(PathBuf::from("/data"))),
             &mut FakeStreamHolder::new().streams(),
         );
 
         assert_eq!(config.ui_port, DEFAULT_UI_PORT);
+        assert_eq!(config.ui_tls_opt, None);
+        assert_eq!(config.ui_auth_opt, None);
+        assert_eq!(config.ui_grpc_port_opt, None);
+        assert_eq!(config.restart_policy, RestartPolicy::default());
     }
 
     #[test]
     fn parse_args_creates_configuration_with_values() {
         let args = ArgsBuilder::new()
             .opt("--initialization")
-            .param("--ui-port", "4321");
+            .param("--ui-port", "4321")
+            .param("--ui-password", "booga")
+            .param("--ui-read-only-password", "agoob")
+            .param("--ui-grpc-port", "5337");
         let mut config = InitializationConfig::default();
         let vcls: Vec<Box<dyn VirtualCommandLine>> =
             vec![Box::new(CommandLineVcl::new(args.into()))];
@@ -109,9 +192,161 @@ mod tests {
         initialization::parse_args(
             &multi_config,
             &mut config,
+            &MockDirsWrapper::new().data_dir_result(Some(PathBuf::from("/data"))),
             &mut FakeStreamHolder::new().streams(),
         );
 
         assert_eq!(config.ui_port, 4321);
+        assert_eq!(
+            config.ui_auth_opt,
+            Some(UiAuthConfig {
+                full_password: "booga".to_string(),
+                read_only_password_opt: Some("agoob".to_string()),
+            })
+        );
+        assert_eq!(config.ui_grpc_port_opt, Some(5337));
+    }
+
+    #[test]
+    fn parse_args_keeps_a_generated_certificate_in_the_masq_data_directory() {
+        let args = ArgsBuilder::new()
+            .opt("--initialization")
+            .param("--ui-tls", "ON");
+        let mut config = InitializationConfig::default();
+        let vcls: Vec<Box<dyn VirtualCommandLine>> =
+            vec![Box::new(CommandLineVcl::new(args.into()))];
+        let multi_config = make_new_test_multi_config(&app(), vcls).unwrap();
+
+        initialization::parse_args(
+            &multi_config,
+            &mut config,
+            &MockDirsWrapper::new().data_dir_result(Some(PathBuf::from("/data"))),
+            &mut FakeStreamHolder::new().streams(),
+        );
+
+        assert_eq!(
+            config.ui_tls_opt,
+            Some(UiTlsConfig {
+                certificate_path: PathBuf::from("/data/MASQ/ui-tls-certificate.pem"),
+                key_path: PathBuf::from("/data/MASQ/ui-tls-key.pem"),
+                generate: true,
+                interfaces: vec![],
+                allowlist: vec![],
+                client_ca_path_opt: None,
+            })
+        );
+    }
+
+    #[test]
+    fn parse_args_uses_the_certificate_supplied() {
+        let args = ArgsBuilder::new()
+            .opt("--initialization")
+            .param("--ui-tls", "on")
+            .param("--ui-tls-certificate", "/etc/masq/cert.pem")
+            .param("--ui-tls-key", "/etc/masq/key.pem");
+        let mut config = InitializationConfig::default();
+        let vcls: Vec<Box<dyn VirtualCommandLine>> =
+            vec![Box::new(CommandLineVcl::new(args.into()))];
+        let multi_config = make_new_test_multi_config(&app(), vcls).unwrap();
+
+        initialization::parse_args(
+            &multi_config,
+            &mut config,
+            &MockDirsWrapper::new().data_dir_result(Some(PathBuf::from("/data"))),
+            &mut FakeStreamHolder::new().streams(),
+        );
+
+        assert_eq!(
+            config.ui_tls_opt,
+            Some(UiTlsConfig {
+                certificate_path: PathBuf::from("/etc/masq/cert.pem"),
+                key_path: PathBuf::from("/etc/masq/key.pem"),
+                generate: false,
+                interfaces: vec![],
+                allowlist: vec![],
+                client_ca_path_opt: None,
+            })
+        );
+    }
+
+    #[test]
+    fn parse_args_reads_interfaces_allowlist_and_client_ca() {
+        let args = ArgsBuilder::new()
+            .opt("--initialization")
+            .param("--ui-tls", "on")
+            .param("--ui-interfaces", "10.8.0.1,192.168.0.5")
+            .param("--ui-allowlist", "10.8.0.0/24,203.0.113.7")
+            .param("--ui-tls-client-ca", "/etc/masq/ca.pem");
+        let mut config = InitializationConfig::default();
+        let vcls: Vec<Box<dyn VirtualCommandLine>> =
+            vec![Box::new(CommandLineVcl::new(args.into()))];
+        let multi_config = make_new_test_multi_config(&app(), vcls).unwrap();
+
+        initialization::parse_args(
+            &multi_config,
+            &mut config,
+            &MockDirsWrapper::new().data_dir_result(Some(PathBuf::from("/data"))),
+            &mut FakeStreamHolder::new().streams(),
+        );
+
+        let tls_config = config.ui_tls_opt.unwrap();
+        assert_eq!(
+            tls_config.interfaces,
+            vec![
+                IpAddr::from_str("10.8.0.1").unwrap(),
+                IpAddr::from_str("192.168.0.5").unwrap()
+            ]
+        );
+        assert_eq!(
+            tls_config.allowlist,
+            vec![
+                IpNetwork::from_str("10.8.0.0/24").unwrap(),
+                IpNetwork::from_str("203.0.113.7/32").unwrap()
+            ]
+        );
+        assert_eq!(
+            tls_config.client_ca_path_opt,
+            Some(PathBuf::from("/etc/masq/ca.pem"))
+        );
+    }
+
+    #[test]
+    fn parse_args_reads_the_restart_policy() {
+        let args = ArgsBuilder::new()
+            .opt("--initialization")
+            .param("--restart-max-retries", "4")
+            .param("--restart-backoff", "30");
+        let mut config = InitializationConfig::default();
+        let vcls: Vec<Box<dyn VirtualCommandLine>> =
+            vec![Box::new(CommandLineVcl::new(args.into()))];
+        let multi_config = make_new_test_multi_config(&app(), vcls).unwrap();
+
+        initialization::parse_args(
+            &multi_config,
+            &mut config,
+            &MockDirsWrapper::new().data_dir_result(Some(PathBuf::from("/data"))),
+            &mut FakeStreamHolder::new().streams(),
+        );
+
+        assert_eq!(
+            config.restart_policy,
+            RestartPolicy {
+                max_retries: 4,
+                backoff: Duration::from_secs(30),
+            }
+        );
+    }
+
+    #[test]
+    fn a_restart_backoff_of_zero_is_refused() {
+        let args = ArgsBuilder::new()
+            .opt("--initialization")
+            .param("--restart-backoff", "0");
+        let vcls: Vec<Box<dyn VirtualCommandLine>> =
+            vec![Box::new(CommandLineVcl::new(args.into()))];
+
+        let result = make_new_test_multi_config(&app(), vcls).err().unwrap();
+
+        assert_eq!(result.param_errors[0].parameter, "restart-backoff");
     }
 }
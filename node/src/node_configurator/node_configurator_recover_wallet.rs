@@ -1,24 +1,30 @@
 // Copyright (c) 2017-2019, Substratum LLC (https://substratum.net) and/or its affiliates. All rights reserved.
 
+use crate::blockchain::bip32::Bip32ECKeyPair;
 use crate::blockchain::bip39::Bip39;
 use crate::db_config::persistent_configuration::PersistentConfiguration;
+use crate::node_configurator::prompt_backend::PromptBackend;
 use crate::node_configurator::{
     app_head, check_for_past_initialization, common_validators, consuming_wallet_arg,
     create_wallet, earning_wallet_arg, flushed_write, language_arg, mnemonic_passphrase_arg,
-    prepare_initialization_mode, request_password_with_confirmation, request_password_with_retry,
-    update_db_password, DirsWrapper, Either, NodeConfigurator, RealDirsWrapper,
-    WalletCreationConfig, WalletCreationConfigMaker, DB_PASSWORD_HELP, EARNING_WALLET_HELP,
+    mnemonic_passphrase_error, prepare_initialization_mode, prompt_backend,
+    request_password_with_confirmation, request_password_with_retry, update_db_password,
+    DirsWrapper, Either, NodeConfigurator, RealDirsWrapper, WalletCreationConfig,
+    WalletCreationConfigMaker, DB_PASSWORD_HELP, EARNING_WALLET_HELP,
 };
+use crate::sub_lib::cli_output;
 use crate::sub_lib::cryptde::PlainData;
+use crate::sub_lib::wallet::Wallet;
 use bip39::{Language, Mnemonic};
 use clap::{value_t, values_t, App, Arg};
 use indoc::indoc;
 use masq_lib::command::StdStreams;
 use masq_lib::multi_config::MultiConfig;
 use masq_lib::shared_schema::{
-    chain_arg, data_directory_arg, db_password_arg, real_user_arg, ConfiguratorError,
+    chain_arg, data_directory_arg, db_password_arg, output_arg, password_prompt_arg, real_user_arg,
+    ConfiguratorError, ParamError,
 };
-use masq_lib::utils::exit_process;
+use serde_json::json;
 
 pub struct NodeConfiguratorRecoverWallet {
     dirs_wrapper: Box<dyn DirsWrapper>,
@@ -40,6 +46,11 @@ impl NodeConfigurator<WalletCreationConfig> for NodeConfiguratorRecoverWallet {
 
         update_db_password(&config, persistent_config)?;
         create_wallet(&config, persistent_config)?;
+        if let Some(ref output) = value_m!(multi_config, "output", String) {
+            if output.eq_ignore_ascii_case("json") {
+                Self::report_wallets(streams, &config)
+            }
+        }
 
         Ok(config)
     }
@@ -84,13 +95,14 @@ impl WalletCreationConfigMaker for NodeConfiguratorRecoverWallet {
         &self,
         multi_config: &MultiConfig,
         streams: &mut StdStreams,
-    ) -> String {
+    ) -> Result<String, ConfiguratorError> {
         match value_m!(multi_config, "mnemonic-passphrase", String) {
-            Some(mp) => mp,
-            None => match Self::request_mnemonic_passphrase(streams) {
-                Some(mp) => mp,
-                None => "".to_string(),
-            },
+            Some(mp) => Ok(mp),
+            None => Ok(Self::request_mnemonic_passphrase(
+                streams,
+                prompt_backend(multi_config).as_ref(),
+            )?
+            .unwrap_or_default()),
         }
     }
 
@@ -101,12 +113,14 @@ impl WalletCreationConfigMaker for NodeConfiguratorRecoverWallet {
         mnemonic_passphrase: &str,
         _consuming_derivation_path: &str,
         _earning_wallet_info: &Either<String, String>,
-    ) -> PlainData {
+    ) -> Result<PlainData, ConfiguratorError> {
         let language_str =
             value_m!(multi_config, "language", String).expect("--language is not defaulted");
         let language = Bip39::language_from_name(&language_str);
-        let mnemonic = Self::get_mnemonic(language, multi_config, streams);
-        PlainData::new(Bip39::seed(&mnemonic, &mnemonic_passphrase).as_ref())
+        let mnemonic = Self::get_mnemonic(language, multi_config, streams)?;
+        Ok(PlainData::new(
+            Bip39::seed(&mnemonic, &mnemonic_passphrase).as_ref(),
+        ))
     }
 }
 
@@ -151,6 +165,8 @@ impl NodeConfiguratorRecoverWallet {
                         .help(MNEMONIC_HELP),
                 )
                 .arg(mnemonic_passphrase_arg())
+                .arg(output_arg())
+                .arg(password_prompt_arg())
                 .arg(real_user_arg())
                 .arg(db_password_arg(DB_PASSWORD_HELP)),
         }
@@ -163,24 +179,60 @@ impl NodeConfiguratorRecoverWallet {
         persistent_config: &dyn PersistentConfiguration,
     ) -> Result<WalletCreationConfig, ConfiguratorError> {
         match persistent_config.mnemonic_seed_exists() {
-            Ok(true) => exit_process(
-                1,
-                "Can't recover wallets: mnemonic seed has already been created",
-            ),
+            Ok(true) => {
+                return Err(ConfiguratorError::failed(
+                    "recover-wallet",
+                    "Can't recover wallets: mnemonic seed has already been created",
+                ))
+            }
             Ok(false) => (),
             Err(pce) => return Err(pce.into_configurator_error("seed")),
         }
-        Ok(self.make_wallet_creation_config(multi_config, streams))
+        self.make_wallet_creation_config(multi_config, streams)
+    }
+
+    // Nothing secret goes out: the mnemonic phrase came from the user, and the seed stays in the database
+    fn report_wallets(streams: &mut StdStreams, config: &WalletCreationConfig) {
+        let consuming_wallet_opt = config.derivation_path_info_opt.as_ref().and_then(|info| {
+            info.consuming_derivation_path_opt.as_ref().map(|path| {
+                let keypair = Bip32ECKeyPair::from_raw(info.mnemonic_seed.as_slice(), path)
+                    .expect("Consuming derivation path was derived when the config was made");
+                json!({
+                    "derivationPath": path,
+                    "address": Wallet::from(keypair).to_string(),
+                })
+            })
+        });
+        let earning_wallet_opt = config
+            .earning_wallet_address_opt
+            .as_ref()
+            .map(|address| json!({ "address": address }));
+        cli_output::write(
+            streams.stdout,
+            &cli_output::success(
+                "recoverWallet",
+                json!({
+                    "consumingWallet": consuming_wallet_opt,
+                    "earningWallet": earning_wallet_opt,
+                }),
+            ),
+        );
     }
 
-    fn request_mnemonic_passphrase(streams: &mut StdStreams) -> Option<String> {
+    fn request_mnemonic_passphrase(
+        streams: &mut StdStreams,
+        backend: &dyn PromptBackend,
+    ) -> Result<Option<String>, ConfiguratorError> {
         flushed_write(
             streams.stdout,
             "\nPlease enter the passphrase for your mnemonic, or Enter if there is none.\n\
              You will encrypt your wallet in a following step...\n",
         );
-        match request_password_with_retry("  Mnemonic passphrase: ", streams, |streams| {
+        match request_password_with_retry(streams, backend, |streams| {
             request_password_with_confirmation(
+                backend,
+                "mnemonic-passphrase",
+                "  Mnemonic passphrase: ",
                 "  Confirm mnemonic passphrase: ",
                 "\nPassphrases do not match.",
                 streams,
@@ -194,12 +246,12 @@ impl NodeConfiguratorRecoverWallet {
                         "\nWhile ill-advised, proceeding with no mnemonic passphrase.\nPress Enter to continue...",
                     );
                     let _ = streams.stdin.read(&mut [0u8]).is_ok();
-                    None
+                    Ok(None)
                 } else {
-                    Some(mp)
+                    Ok(Some(mp))
                 }
             }
-            Err(e) => panic!("{:?}", e),
+            Err(e) => Err(mnemonic_passphrase_error(e)),
         }
     }
 
@@ -207,11 +259,11 @@ impl NodeConfiguratorRecoverWallet {
         language: Language,
         multi_config: &MultiConfig,
         streams: &mut StdStreams,
-    ) -> Mnemonic {
+    ) -> Result<Mnemonic, ConfiguratorError> {
         let phrase_words = {
             let arg_phrase_words = values_m!(multi_config, "mnemonic", String);
             if arg_phrase_words.is_empty() {
-                Self::request_mnemonic_phrase(streams)
+                Self::request_mnemonic_phrase(streams)?
             } else {
                 arg_phrase_words
             }
@@ -219,25 +271,41 @@ impl NodeConfiguratorRecoverWallet {
         let phrase = phrase_words.join(" ");
         match Validators::validate_mnemonic_words(phrase.clone(), language) {
             Ok(_) => (),
-            Err(e) => exit_process(1, &e),
+            Err(e) => {
+                return Err(ConfiguratorError::new(vec![ParamError::new(
+                    "mnemonic", &e,
+                )]))
+            }
         }
-        Mnemonic::from_phrase(phrase, language).expect("Error creating Mnemonic")
+        Ok(Mnemonic::from_phrase(phrase, language).expect("Error creating Mnemonic"))
     }
 
-    fn request_mnemonic_phrase(streams: &mut StdStreams) -> Vec<String> {
+    fn request_mnemonic_phrase(streams: &mut StdStreams) -> Result<Vec<String>, ConfiguratorError> {
         flushed_write(streams.stdout, "\nPlease provide your wallet's mnemonic phrase.\nIt must be 12, 15, 18, 21, or 24 words long.\n");
         flushed_write(streams.stdout, "Mnemonic phrase: ");
         let mut buf = [0u8; 16384];
         let phrase = match streams.stdin.read(&mut buf) {
-            Ok(len) => String::from_utf8(Vec::from(&buf[0..len]))
-                .expect("Mnemonic may not contain non-UTF-8 characters"),
-            Err(e) => panic!("{:?}", e),
+            Ok(len) => match String::from_utf8(Vec::from(&buf[0..len])) {
+                Ok(phrase) => phrase,
+                Err(_) => {
+                    return Err(ConfiguratorError::new(vec![ParamError::new(
+                        "mnemonic",
+                        "Mnemonic may not contain non-UTF-8 characters",
+                    )]))
+                }
+            },
+            Err(e) => {
+                return Err(ConfiguratorError::failed(
+                    "mnemonic",
+                    &format!("Couldn't read the mnemonic phrase: {}", e),
+                ))
+            }
         };
-        phrase
+        Ok(phrase
             .split(|c| " \t\n".contains(c))
             .filter(|s| !s.is_empty())
             .map(|s| s.trim().to_string())
-            .collect()
+            .collect())
     }
 }
 
@@ -260,7 +328,6 @@ impl Validators {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::blockchain::bip32::Bip32ECKeyPair;
     use crate::bootstrapper::RealUser;
     use crate::database::db_initializer;
     use crate::database::db_initializer::DbInitializer;
@@ -268,11 +335,12 @@ mod tests {
     use crate::db_config::persistent_configuration::{
         PersistentConfigError, PersistentConfigurationReal,
     };
+    use crate::node_configurator::prompt_backend::TtyPromptBackend;
     use crate::node_configurator::{initialize_database, DerivationPathWalletInfo};
     use crate::sub_lib::cryptde::PlainData;
     use crate::sub_lib::utils::make_new_test_multi_config;
     use crate::sub_lib::wallet::{
-        Wallet, DEFAULT_CONSUMING_DERIVATION_PATH, DEFAULT_EARNING_DERIVATION_PATH,
+        DEFAULT_CONSUMING_DERIVATION_PATH, DEFAULT_EARNING_DERIVATION_PATH,
     };
     use crate::test_utils::persistent_configuration_mock::PersistentConfigurationMock;
     use crate::test_utils::*;
@@ -443,7 +511,7 @@ mod tests {
             .configure(args_vec.as_slice(), &mut FakeStreamHolder::new().streams())
             .unwrap();
 
-        let persistent_config = initialize_database(&home_dir, DEFAULT_CHAIN_ID);
+        let persistent_config = initialize_database(&home_dir, DEFAULT_CHAIN_ID).unwrap();
         assert_eq!(persistent_config.check_password(Some(password)), Ok(true));
         let expected_mnemonic = Mnemonic::from_phrase(phrase, Language::Spanish).unwrap();
         let seed = Seed::new(&expected_mnemonic, "Mortimer");
@@ -465,6 +533,63 @@ mod tests {
         );
     }
 
+    #[test]
+    fn configure_reports_the_recovered_wallets_for_json_output() {
+        let _clap_guard = ClapGuard::new();
+        let home_dir = ensure_node_home_directory_exists(
+            "node_configurator_recover_wallet",
+            "configure_reports_the_recovered_wallets_for_json_output",
+        );
+        let phrase = "llanto elipse chaleco factor setenta dental moneda rasgo gala rostro taco nudillo orador temor puesto";
+        let consuming_path = "m/44'/60'/0'/77/78";
+        let args_vec: Vec<String> = ArgsBuilder::new()
+            .opt("--recover-wallet")
+            .param("--chain", TEST_DEFAULT_CHAIN_NAME)
+            .param("--data-directory", home_dir.to_str().unwrap())
+            .param("--db-password", "secret-db-password")
+            .param("--consuming-wallet", consuming_path)
+            .param(
+                "--earning-wallet",
+                "0x0123456789012345678901234567890123456789",
+            )
+            .param("--language", "español")
+            .param("--mnemonic", phrase)
+            .param("--mnemonic-passphrase", "Mortimer")
+            .param("--real-user", "123:456:/home/booga")
+            .param("--output", "json")
+            .into();
+        let subject = NodeConfiguratorRecoverWallet::new();
+        let mut holder = FakeStreamHolder::new();
+
+        subject
+            .configure(args_vec.as_slice(), &mut holder.streams())
+            .unwrap();
+
+        let seed = Seed::new(
+            &Mnemonic::from_phrase(phrase, Language::Spanish).unwrap(),
+            "Mortimer",
+        );
+        let consuming_wallet =
+            Wallet::from(Bip32ECKeyPair::from_raw(seed.as_ref(), consuming_path).unwrap());
+        let result: serde_json::Value = serde_json::from_str(&holder.stdout.get_string()).unwrap();
+        assert_eq!(
+            result,
+            json!({
+                "version": 1,
+                "mode": "recoverWallet",
+                "result": {
+                    "consumingWallet": {
+                        "derivationPath": consuming_path,
+                        "address": consuming_wallet.to_string(),
+                    },
+                    "earningWallet": {
+                        "address": "0x0123456789012345678901234567890123456789",
+                    },
+                },
+            })
+        );
+    }
+
     #[test]
     fn parse_args_creates_configuration_with_defaults() {
         running_test();
@@ -529,9 +654,6 @@ mod tests {
     }
 
     #[test]
-    #[should_panic(
-        expected = "\"one two three four five six seven eight nine ten eleven twelve\" is not valid for English (invalid word in phrase)"
-    )]
     fn mnemonic_argument_fails_with_invalid_words() {
         running_test();
         let args = ArgsBuilder::new()
@@ -547,13 +669,19 @@ mod tests {
         let vcl = Box::new(CommandLineVcl::new(args.into()));
         let multi_config = make_new_test_multi_config(&subject.app, vec![vcl]).unwrap();
 
-        subject
-            .parse_args(
-                &multi_config,
-                &mut FakeStreamHolder::new().streams(),
-                &make_default_persistent_configuration(),
-            )
-            .unwrap();
+        let result = subject.parse_args(
+            &multi_config,
+            &mut FakeStreamHolder::new().streams(),
+            &make_default_persistent_configuration(),
+        );
+
+        assert_eq!(
+            result,
+            Err(ConfiguratorError::new(vec![ParamError::new(
+                "mnemonic",
+                "\"one two three four five six seven eight nine ten eleven twelve\" is not valid for English (invalid word in phrase)"
+            )]))
+        );
     }
 
     #[test]
@@ -565,9 +693,12 @@ mod tests {
             stderr: &mut ByteArrayWriter::new(),
         };
 
-        let actual = NodeConfiguratorRecoverWallet::request_mnemonic_passphrase(streams);
+        let actual = NodeConfiguratorRecoverWallet::request_mnemonic_passphrase(
+            streams,
+            &TtyPromptBackend {},
+        );
 
-        assert_eq!(actual, Some("a very poor passphrase".to_string()));
+        assert_eq!(actual, Ok(Some("a very poor passphrase".to_string())));
         assert_eq!(
             stdout_writer.get_string(),
             "\nPlease enter the passphrase for your mnemonic, or Enter if there is none.\n\
@@ -586,9 +717,12 @@ mod tests {
             stderr: &mut ByteArrayWriter::new(),
         };
 
-        let actual = NodeConfiguratorRecoverWallet::request_mnemonic_passphrase(streams);
+        let actual = NodeConfiguratorRecoverWallet::request_mnemonic_passphrase(
+            streams,
+            &TtyPromptBackend {},
+        );
 
-        assert_eq!(actual, None);
+        assert_eq!(actual, Ok(None));
         assert_eq!(
             stdout_writer.get_string(),
             "\nPlease enter the passphrase for your mnemonic, or Enter if there is none.\n\
@@ -607,9 +741,12 @@ mod tests {
             stderr: &mut ByteArrayWriter::new(),
         };
 
-        let actual = NodeConfiguratorRecoverWallet::request_mnemonic_passphrase(streams);
+        let actual = NodeConfiguratorRecoverWallet::request_mnemonic_passphrase(
+            streams,
+            &TtyPromptBackend {},
+        );
 
-        assert_eq!(actual, None);
+        assert_eq!(actual, Ok(None));
         assert_eq!(
             stdout_writer.get_string(),
             "\nPlease enter the passphrase for your mnemonic, or Enter if there is none.\n\
@@ -622,12 +759,11 @@ mod tests {
     }
 
     #[test]
-    #[should_panic(expected = "Can't recover wallets: mnemonic seed has already been created")]
-    fn preexisting_mnemonic_seed_causes_collision_and_panics() {
+    fn preexisting_mnemonic_seed_causes_collision_and_fails() {
         running_test();
         let data_directory = ensure_node_home_directory_exists(
             "node_configurator_recover_wallet",
-            "preexisting_mnemonic_seed_causes_collision_and_panics",
+            "preexisting_mnemonic_seed_causes_collision_and_fails",
         );
 
         let conn = db_initializer::DbInitializerReal::new()
@@ -650,13 +786,38 @@ mod tests {
         let vcl = Box::new(CommandLineVcl::new(args.into()));
         let multi_config = make_new_test_multi_config(&subject.app, vec![vcl]).unwrap();
 
-        subject
-            .parse_args(
-                &multi_config,
-                &mut FakeStreamHolder::new().streams(),
-                &persistent_config,
-            )
-            .unwrap();
+        let result = subject.parse_args(
+            &multi_config,
+            &mut FakeStreamHolder::new().streams(),
+            &persistent_config,
+        );
+
+        assert_eq!(
+            result,
+            Err(ConfiguratorError::failed(
+                "recover-wallet",
+                "Can't recover wallets: mnemonic seed has already been created"
+            ))
+        );
+    }
+
+    #[test]
+    fn request_mnemonic_phrase_rejects_non_utf8_input() {
+        let mut streams = StdStreams {
+            stdin: &mut Cursor::new(&b"aim special \xff\xfe peace\n"[..]),
+            stdout: &mut ByteArrayWriter::new(),
+            stderr: &mut ByteArrayWriter::new(),
+        };
+
+        let result = NodeConfiguratorRecoverWallet::request_mnemonic_phrase(&mut streams);
+
+        assert_eq!(
+            result,
+            Err(ConfiguratorError::new(vec![ParamError::new(
+                "mnemonic",
+                "Mnemonic may not contain non-UTF-8 characters"
+            )]))
+        );
     }
 
     #[test]
@@ -668,7 +829,7 @@ mod tests {
             stderr: &mut ByteArrayWriter::new(),
         };
 
-        let result = NodeConfiguratorRecoverWallet::request_mnemonic_phrase(&mut streams);
+        let result = NodeConfiguratorRecoverWallet::request_mnemonic_phrase(&mut streams).unwrap();
 
         assert_eq!(
             result,
@@ -7,8 +7,13 @@ use clap::App;
 use indoc::indoc;
 use masq_lib::command::StdStreams;
 use masq_lib::crash_point::CrashPoint;
-use masq_lib::shared_schema::{shared_app, ui_port_arg};
-use masq_lib::shared_schema::{ConfiguratorError, UI_PORT_HELP};
+use masq_lib::shared_schema::{
+    health_port_arg, log_retention_arg, log_rotation_age_arg, log_rotation_size_arg, shared_app,
+    ui_allowlist_arg, ui_grpc_port_arg, ui_interfaces_arg, ui_password_arg, ui_port_arg,
+    ui_read_only_password_arg, ui_rest_port_arg, ui_tls_arg, ui_tls_certificate_arg,
+    ui_tls_client_ca_arg, ui_tls_key_arg,
+};
+use masq_lib::shared_schema::{ConfiguratorError, UI_GRPC_PORT_HELP, UI_PORT_HELP};
 
 pub struct NodeConfiguratorStandardPrivileged {
     dirs_wrapper: Box<dyn DirsWrapper>,
@@ -68,7 +73,7 @@ impl NodeConfigurator<BootstrapperConfig> for NodeConfiguratorStandardUnprivileg
         let mut persistent_config = initialize_database(
             &self.privileged_config.data_directory,
             self.privileged_config.blockchain_bridge_config.chain_id,
-        );
+        )?;
         let mut unprivileged_config = BootstrapperConfig::new();
         let multi_config = standard::make_service_mode_multi_config(
             self.dirs_wrapper.as_ref(),
@@ -83,6 +88,11 @@ impl NodeConfigurator<BootstrapperConfig> for NodeConfiguratorStandardUnprivileg
             Some(persistent_config.as_mut()),
         )?;
         standard::configure_database(&unprivileged_config, persistent_config.as_mut())?;
+        unprivileged_config.earning_wallet_change_opt =
+            match persistent_config.earning_wallet_change() {
+                Ok(change_opt) => change_opt,
+                Err(pce) => return Err(pce.into_configurator_error("earning-wallet")),
+            };
         Ok(unprivileged_config)
     }
 }
@@ -136,7 +146,22 @@ const HELP_TEXT: &str = indoc!(
 );
 
 pub fn app() -> App<'static, 'static> {
-    shared_app(app_head().after_help(HELP_TEXT)).arg(ui_port_arg(&UI_PORT_HELP))
+    shared_app(app_head().after_help(HELP_TEXT))
+        .arg(ui_port_arg(&UI_PORT_HELP))
+        .arg(ui_tls_arg())
+        .arg(ui_tls_certificate_arg())
+        .arg(ui_tls_key_arg())
+        .arg(ui_interfaces_arg())
+        .arg(ui_allowlist_arg())
+        .arg(ui_tls_client_ca_arg())
+        .arg(ui_password_arg())
+        .arg(ui_read_only_password_arg())
+        .arg(ui_rest_port_arg())
+        .arg(ui_grpc_port_arg(UI_GRPC_PORT_HELP))
+        .arg(health_port_arg())
+        .arg(log_rotation_size_arg())
+        .arg(log_rotation_age_arg())
+        .arg(log_retention_arg())
 }
 
 pub mod standard {
@@ -149,36 +174,62 @@ pub mod standard {
 
     use crate::blockchain::bip32::Bip32ECKeyPair;
     use crate::blockchain::blockchain_interface::chain_id_from_name;
+    use crate::blockchain::payment_adapter::{default_payment_token, PaymentToken};
     use crate::bootstrapper::PortConfiguration;
+    use crate::db_config::password_budget::check_password_within_budget;
     use crate::db_config::persistent_configuration::{
         PersistentConfigError, PersistentConfiguration,
     };
+    use crate::entry_dns::dns_socket_server::DNS_PORT;
+    use crate::geo_db::{GeoDbConfig, DEFAULT_GEO_DB_UPDATE_INTERVAL_HOURS};
     use crate::http_request_start_finder::HttpRequestDiscriminatorFactory;
+    use crate::neighborhood::route_search::DEFAULT_ROUTE_SEARCH_THREADS;
     use crate::node_configurator::{
-        data_directory_from_context, determine_config_file_path,
-        real_user_data_directory_opt_and_chain_name, request_existing_db_password, DirsWrapper,
+        data_directory_from_context, derive_wallet, determine_config_file_path, flushed_write,
+        prompt_backend, real_user_data_directory_opt_and_chain_name, request_existing_db_password,
+        ui_auth_config_from, ui_tls_config_from, DirsWrapper,
     };
+    use crate::socks_discriminator_factory::SocksDiscriminatorFactory;
     use crate::sub_lib::accountant::DEFAULT_EARNING_WALLET;
+    use crate::sub_lib::clock_skew::{DEFAULT_CLOCK_SKEW_TOLERANCE_SECS, DEFAULT_TIME_SERVERS};
+    use crate::sub_lib::cpu_affinity::parse_cpu_list;
     use crate::sub_lib::cryptde::{CryptDE, PlainData, PublicKey};
     use crate::sub_lib::cryptde_null::CryptDENull;
     use crate::sub_lib::cryptde_real::CryptDEReal;
+    use crate::sub_lib::data_cap::{DataCapConfig, BYTES_PER_GB};
+    use crate::sub_lib::descriptor_signature::{is_signed_by, verify_signed_descriptor};
+    use crate::sub_lib::key_rotation::{KeyRotationConfig, DEFAULT_KEY_OVERLAP_HOURS};
+    use crate::sub_lib::log_rotation::{
+        LogRotationConfig, DEFAULT_LOG_RETENTION, DEFAULT_LOG_ROTATION_AGE_HOURS,
+        DEFAULT_LOG_ROTATION_SIZE_MB,
+    };
     use crate::sub_lib::neighborhood::{
         NeighborhoodConfig, NeighborhoodMode, NodeDescriptor, DEFAULT_RATE_PACK,
     };
     use crate::sub_lib::node_addr::NodeAddr;
+    use crate::sub_lib::proxy_client::{DEFAULT_DNS_CACHE_SIZE, DEFAULT_DNS_LOOKUP_THREADS};
+    use crate::sub_lib::proxy_server::{
+        EncryptedSniFallback, MultipathMode, SocksConfig, SocksCredentials,
+    };
     use crate::sub_lib::utils::make_new_multi_config;
-    use crate::sub_lib::wallet::Wallet;
+    use crate::sub_lib::wallet::{Wallet, WalletChange};
     use crate::tls_discriminator_factory::TlsDiscriminatorFactory;
+    use crate::updater::{UpdateCheckConfig, DEFAULT_UPDATE_CHECK_INTERVAL_HOURS};
     use itertools::Itertools;
     use masq_lib::constants::{
         DEFAULT_CHAIN_NAME, DEFAULT_GAS_PRICE, DEFAULT_UI_PORT, HTTP_PORT, TLS_PORT,
     };
+    use masq_lib::data_cap::DataCapRate;
+    use masq_lib::exit_policy::ExitPolicy;
     use masq_lib::multi_config::{CommandLineVcl, ConfigFileVcl, EnvironmentVcl, MultiConfig};
-    use masq_lib::shared_schema::{ConfiguratorError, ParamError};
+    use masq_lib::secure_dns::SecureDnsServer;
+    use masq_lib::shared_schema::{ConfiguratorError, ParamError, ParamErrorCode};
     use masq_lib::test_utils::utils::DEFAULT_CHAIN_ID;
+    use masq_lib::utils::parse_port_list;
     use rustc_hex::FromHex;
     use std::convert::TryInto;
     use std::str::FromStr;
+    use std::time::{Duration, SystemTime};
 
     pub fn make_service_mode_multi_config<'a>(
         dirs_wrapper: &dyn DirsWrapper,
@@ -232,11 +283,20 @@ pub mod standard {
         privileged_config
             .blockchain_bridge_config
             .blockchain_service_url = value_m!(multi_config, "blockchain-service-url", String);
+        privileged_config.blockchain_bridge_config.faucet_url_opt =
+            value_m!(multi_config, "faucet-url", String);
+        privileged_config
+            .blockchain_bridge_config
+            .price_feed_url_opt = value_m!(multi_config, "price-feed-url", String);
 
         let (real_user, data_directory_opt, chain_name) =
             real_user_data_directory_opt_and_chain_name(dirs_wrapper, &multi_config);
-        let directory =
-            data_directory_from_context(dirs_wrapper, &real_user, &data_directory_opt, &chain_name);
+        let directory = data_directory_from_context(
+            dirs_wrapper,
+            &real_user,
+            &data_directory_opt,
+            &chain_name,
+        )?;
         privileged_config.real_user = real_user;
         privileged_config.data_directory = directory;
         privileged_config.blockchain_bridge_config.chain_id = chain_id_from_name(&chain_name);
@@ -254,16 +314,209 @@ pub mod standard {
                 .collect(),
             None => vec![SocketAddr::new(IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1)), 53)],
         };
+        privileged_config.secure_dns_servers =
+            match value_m!(multi_config, "secure-dns-servers", String) {
+                Some(joined_servers) => joined_servers
+                    .split(',')
+                    .map(|server_str| {
+                        SecureDnsServer::from_str(server_str)
+                            .expect("Bad clap validation for secure-dns-servers")
+                    })
+                    .collect(),
+                None => vec![],
+            };
+        privileged_config.dns_cache_size =
+            value_m!(multi_config, "dns-cache-size", usize).unwrap_or(DEFAULT_DNS_CACHE_SIZE);
+        privileged_config.dns_lookup_threads = value_m!(multi_config, "dns-lookup-threads", usize)
+            .unwrap_or(DEFAULT_DNS_LOOKUP_THREADS);
+        privileged_config.route_search_threads =
+            value_m!(multi_config, "route-search-threads", usize)
+                .unwrap_or(DEFAULT_ROUTE_SEARCH_THREADS);
+        privileged_config.exit_policy = match value_m!(multi_config, "exit-policy", String) {
+            Some(policy_str) => {
+                ExitPolicy::from_str(&policy_str).expect("Bad clap validation for exit-policy")
+            }
+            None => ExitPolicy::default(),
+        };
+        privileged_config.reputation_lists = value_m!(multi_config, "reputation-lists", String)
+            .map(|joined_lists| {
+                joined_lists
+                    .split(',')
+                    .map(|list| list.trim_end_matches('.').to_lowercase())
+                    .collect()
+            })
+            .unwrap_or_default();
+        privileged_config.stream_idle_timeout_opt =
+            match value_m!(multi_config, "stream-idle-timeout", u64) {
+                Some(0) | None => None,
+                Some(seconds) => Some(Duration::from_secs(seconds)),
+            };
+        privileged_config.multipath_mode =
+            value_m!(multi_config, "multipath", MultipathMode).unwrap_or(MultipathMode::Off);
+        privileged_config.encrypted_sni_fallback =
+            value_m!(multi_config, "encrypted-sni", EncryptedSniFallback)
+                .unwrap_or(EncryptedSniFallback::Refuse);
+
+        privileged_config.dns_port = value_m!(multi_config, "dns-port", u16).unwrap_or(DNS_PORT);
+        privileged_config.proxied_domains_opt = value_m!(multi_config, "proxied-domains", String)
+            .map(|joined_domains| {
+                joined_domains
+                    .split(',')
+                    .map(|domain| domain.trim_end_matches('.').to_lowercase())
+                    .collect()
+            });
+        privileged_config.metrics_port_opt = value_m!(multi_config, "metrics-port", u16);
+        privileged_config.update_check_opt = value_m!(multi_config, "update-check-url", String)
+            .map(|manifest_url| UpdateCheckConfig {
+                manifest_url,
+                public_key: value_m!(multi_config, "update-check-key", String)
+                    .expect("Clap let --update-check-url through without --update-check-key")
+                    .from_hex()
+                    .expect("Bad clap validation for update-check-key"),
+                interval: Duration::from_secs(
+                    3600 * value_m!(multi_config, "update-check-interval", u64)
+                        .unwrap_or(DEFAULT_UPDATE_CHECK_INTERVAL_HOURS),
+                ),
+            });
+        privileged_config.geo_db_opt =
+            value_m!(multi_config, "geo-db-url", String).map(|mirror_url| GeoDbConfig {
+                mirror_url,
+                public_key: value_m!(multi_config, "geo-db-key", String)
+                    .expect("Clap let --geo-db-url through without --geo-db-key")
+                    .from_hex()
+                    .expect("Bad clap validation for geo-db-key"),
+                interval: Duration::from_secs(
+                    3600 * value_m!(multi_config, "geo-db-interval", u64)
+                        .unwrap_or(DEFAULT_GEO_DB_UPDATE_INTERVAL_HOURS),
+                ),
+            });
 
         privileged_config.log_level =
             value_m!(multi_config, "log-level", LevelFilter).unwrap_or(LevelFilter::Warn);
+        privileged_config.log_redaction = match value_m!(multi_config, "log-redaction", String) {
+            Some(ref value) => value.eq_ignore_ascii_case("on"),
+            None => true,
+        };
+        privileged_config.deterministic_seed_opt =
+            value_m!(multi_config, "deterministic-seed", u64);
+        privileged_config.data_cap_opt =
+            value_m!(multi_config, "data-cap", u64).map(|gigabytes| DataCapConfig {
+                monthly_bytes: gigabytes.saturating_mul(BYTES_PER_GB),
+                rates: match value_m!(multi_config, "data-cap-rates", String) {
+                    Some(rates) => rates
+                        .split(',')
+                        .map(|rate| {
+                            DataCapRate::from_str(rate).expect("Validator let bad rate through")
+                        })
+                        .collect(),
+                    None => vec![],
+                },
+            });
+        privileged_config.cpu_affinity = match value_m!(multi_config, "cpu-affinity", String) {
+            Some(cpus) => parse_cpu_list(&cpus).expect("Validator let bad CPU list through"),
+            None => vec![],
+        };
+        privileged_config.port_reselection =
+            match value_m!(multi_config, "port-reselection", String) {
+                Some(ref value) => value.eq_ignore_ascii_case("on"),
+                None => false,
+            };
+        privileged_config.sandbox = match value_m!(multi_config, "sandbox", String) {
+            Some(ref value) => value.eq_ignore_ascii_case("on"),
+            None => true,
+        };
+        privileged_config.local_discovery = match value_m!(multi_config, "local-discovery", String)
+        {
+            Some(ref value) => value.eq_ignore_ascii_case("on"),
+            None => false,
+        };
+        privileged_config.bandwidth_benchmark =
+            match value_m!(multi_config, "bandwidth-benchmark", String) {
+                Some(ref value) => value.eq_ignore_ascii_case("on"),
+                None => false,
+            };
+        privileged_config.hop_timing = match value_m!(multi_config, "hop-timing", String) {
+            Some(ref value) => value.eq_ignore_ascii_case("on"),
+            None => false,
+        };
+        privileged_config.masquerade_profiles =
+            value_m!(multi_config, "masquerade-profiles", String)
+                .map(|joined| {
+                    joined
+                        .split(',')
+                        .map(|profile| profile.to_string())
+                        .collect()
+                })
+                .unwrap_or_default();
+        privileged_config.gossip_recording =
+            match value_m!(multi_config, "gossip-recording", String) {
+                Some(ref value) => value.eq_ignore_ascii_case("on"),
+                None => false,
+            };
+        privileged_config.dump_neighborhood =
+            match value_m!(multi_config, "dump-neighborhood", String) {
+                Some(ref value) => value.eq_ignore_ascii_case("on"),
+                None => false,
+            };
+        privileged_config.protocol_sniffing =
+            match value_m!(multi_config, "protocol-sniffing", String) {
+                Some(ref value) => value.eq_ignore_ascii_case("on"),
+                None => false,
+            };
+        privileged_config.publish_attestations =
+            match value_m!(multi_config, "publish-attestations", String) {
+                Some(ref value) => value.eq_ignore_ascii_case("on"),
+                None => false,
+            };
+        privileged_config.heed_attestations =
+            match value_m!(multi_config, "heed-attestations", String) {
+                Some(ref value) => value.eq_ignore_ascii_case("on"),
+                None => false,
+            };
+        privileged_config.clock_skew_tolerance_secs =
+            value_m!(multi_config, "clock-skew-tolerance", u32)
+                .unwrap_or(DEFAULT_CLOCK_SKEW_TOLERANCE_SECS);
+        privileged_config.time_servers = match value_m!(multi_config, "time-servers", String) {
+            Some(ref value) if value == "off" => vec![],
+            Some(value) => value.split(',').map(|server| server.to_string()).collect(),
+            None => DEFAULT_TIME_SERVERS
+                .iter()
+                .map(|server| server.to_string())
+                .collect(),
+        };
+        privileged_config.descriptor_signature_opt =
+            value_m!(multi_config, "descriptor-signature", String);
 
         privileged_config.ui_gateway_config.ui_port =
             value_m!(multi_config, "ui-port", u16).unwrap_or(DEFAULT_UI_PORT);
+        privileged_config.ui_gateway_config.tls_opt =
+            ui_tls_config_from(multi_config, &privileged_config.data_directory);
+        privileged_config.ui_gateway_config.auth_opt = ui_auth_config_from(multi_config);
+        privileged_config.ui_gateway_config.rest_port_opt =
+            value_m!(multi_config, "ui-rest-port", u16);
+        privileged_config.ui_gateway_config.grpc_port_opt =
+            value_m!(multi_config, "ui-grpc-port", u16);
+        privileged_config.ui_gateway_config.health_port_opt =
+            value_m!(multi_config, "health-port", u16);
+        privileged_config.ui_gateway_config.data_directory =
+            privileged_config.data_directory.clone();
 
         privileged_config.crash_point =
             value_m!(multi_config, "crash-point", CrashPoint).unwrap_or(CrashPoint::None);
 
+        privileged_config.socks_config_opt =
+            value_m!(multi_config, "socks-port", u16).map(|port| SocksConfig {
+                port,
+                credentials_opt: value_m!(multi_config, "socks-credentials", String)
+                    .map(|credentials| socks_credentials_from(&credentials)),
+            });
+        if let Some(socks_config) = &privileged_config.socks_config_opt {
+            privileged_config.port_configurations.insert(
+                socks_config.port,
+                PortConfiguration::new(vec![Box::new(SocksDiscriminatorFactory::new())], false),
+            );
+        }
+
         match value_m!(multi_config, "fake-public-key", String) {
             None => (),
             Some(public_key_str) => {
@@ -274,7 +527,12 @@ pub mod standard {
                         let alias_public_key = PublicKey::new(&key);
                         (main_public_key, alias_public_key)
                     }
-                    Err(e) => panic!("Invalid fake public key: {} ({:?})", public_key_str, e),
+                    Err(e) => {
+                        return Err(ConfiguratorError::new(vec![ParamError::new(
+                            "fake-public-key",
+                            &format!("Invalid fake public key: {} ({:?})", public_key_str, e),
+                        )]))
+                    }
                 };
                 let main_cryptde_null = CryptDENull::from(
                     &main_public_key,
@@ -291,13 +549,28 @@ pub mod standard {
         Ok(())
     }
 
+    fn socks_credentials_from(credentials: &str) -> SocksCredentials {
+        let idx = credentials
+            .find(':')
+            .expect("Bad clap validation for socks-credentials");
+        SocksCredentials {
+            username: credentials[..idx].to_string(),
+            password: credentials[(idx + 1)..].to_string(),
+        }
+    }
+
     pub fn unprivileged_parse_args(
         multi_config: &MultiConfig,
         unprivileged_config: &mut BootstrapperConfig,
         streams: &mut StdStreams<'_>,
-        persistent_config_opt: Option<&mut dyn PersistentConfiguration>,
+        mut persistent_config_opt: Option<&mut dyn PersistentConfiguration>,
     ) -> Result<(), ConfiguratorError> {
         unprivileged_config.clandestine_port_opt = value_m!(multi_config, "clandestine-port", u16);
+        unprivileged_config.additional_clandestine_ports =
+            match value_m!(multi_config, "additional-clandestine-ports", String) {
+                Some(ports) => parse_port_list(&ports).expect("Validator failed"),
+                None => vec![],
+            };
         let user_specified = multi_config.arg_matches().occurrences_of("gas-price") > 0;
         unprivileged_config.blockchain_bridge_config.gas_price = if user_specified {
             value_m!(multi_config, "gas-price", u64).expect("Value disappeared")
@@ -313,7 +586,34 @@ pub mod standard {
                 None => 1,
             }
         };
-        let mnc_result = if let Some(persistent_config) = persistent_config_opt {
+        unprivileged_config
+            .blockchain_bridge_config
+            .payment_token_opt = value_m!(multi_config, "payment-token", String).map(|token| {
+            PaymentToken::from_str(&token).expect("Bad clap validation for payment-token")
+        });
+        if let Some(persistent_config) = persistent_config_opt.as_deref_mut() {
+            check_payment_token(unprivileged_config, persistent_config)?;
+        }
+        unprivileged_config.log_rotation =
+            log_rotation_config(multi_config, persistent_config_opt.as_deref())?;
+        unprivileged_config.key_rotation_opt = key_rotation_config(
+            multi_config,
+            streams,
+            unprivileged_config,
+            persistent_config_opt.as_deref_mut(),
+        )?;
+        unprivileged_config.post_quantum = match value_m!(multi_config, "post-quantum", String) {
+            Some(ref value) => value.eq_ignore_ascii_case("on"),
+            None => false,
+        };
+        // --local-proxy by itself means on
+        unprivileged_config.local_proxy = match value_m!(multi_config, "local-proxy", String) {
+            Some(ref value) => value.eq_ignore_ascii_case("on"),
+            None => multi_config.arg_matches().occurrences_of("local-proxy") > 0,
+        };
+        let mnc_result = if unprivileged_config.local_proxy {
+            make_local_proxy_neighborhood_config(multi_config)
+        } else if let Some(persistent_config) = persistent_config_opt {
             get_wallets(
                 streams,
                 multi_config,
@@ -338,6 +638,140 @@ pub mod standard {
         }
     }
 
+    // A local proxy pays nobody and is paid by nobody, so it doesn't look for wallets or past
+    // neighbors, either of which might need the database password
+    fn make_local_proxy_neighborhood_config(
+        multi_config: &MultiConfig,
+    ) -> Result<NeighborhoodConfig, ConfiguratorError> {
+        match value_m!(multi_config, "neighborhood-mode", String) {
+            Some(ref s) if s != "zero-hop" => Err(ConfiguratorError::conflict(
+                &["local-proxy", "neighborhood-mode"],
+                &format!(
+                    "Node cannot run as a --local-proxy in --neighborhood-mode {}",
+                    s
+                ),
+            )),
+            _ if value_m!(multi_config, "neighbors", String).is_some() => {
+                Err(ConfiguratorError::conflict(
+                    &["local-proxy", "neighbors"],
+                    "Node cannot run as a --local-proxy if --neighbors is specified",
+                ))
+            }
+            _ if value_m!(multi_config, "ip", IpAddr).is_some() => {
+                Err(ConfiguratorError::conflict(
+                    &["local-proxy", "ip"],
+                    "Node cannot run as a --local-proxy if --ip is specified",
+                ))
+            }
+            _ => Ok(NeighborhoodConfig {
+                mode: NeighborhoodMode::ZeroHop,
+            }),
+        }
+    }
+
+    // Each setting comes from the command line if it's there, or else from the database
+    // The database keeps its amounts in the native units of the first token it settled in;
+    // read as another token's, they'd be different amounts altogether
+    fn check_payment_token(
+        config: &BootstrapperConfig,
+        persistent_config: &mut dyn PersistentConfiguration,
+    ) -> Result<(), ConfiguratorError> {
+        let token = config
+            .blockchain_bridge_config
+            .payment_token_opt
+            .clone()
+            .unwrap_or_else(|| default_payment_token(config.blockchain_bridge_config.chain_id));
+        match persistent_config.payment_token() {
+            Ok(None) => persistent_config
+                .set_payment_token(&token.to_string())
+                .map_err(|pce| pce.into_configurator_error("payment-token")),
+            Ok(Some(stored)) => match PaymentToken::from_str(&stored) {
+                Ok(ref stored_token)
+                    if stored_token.contract_address == token.contract_address
+                        && stored_token.decimals == token.decimals =>
+                {
+                    Ok(())
+                }
+                _ => Err(ConfiguratorError::required(
+                    "payment-token",
+                    &format!(
+                        "This database keeps its accounts in {}, so it can't settle in {}; give each \
+                         payment token its own --data-directory",
+                        stored, token
+                    ),
+                )),
+            },
+            Err(pce) => Err(pce.into_configurator_error("payment-token")),
+        }
+    }
+
+    fn log_rotation_config(
+        multi_config: &MultiConfig,
+        persistent_config_opt: Option<&dyn PersistentConfiguration>,
+    ) -> Result<LogRotationConfig, ConfiguratorError> {
+        type Getter =
+            fn(&dyn PersistentConfiguration) -> Result<Option<u64>, PersistentConfigError>;
+        let setting = |name: &str, getter: Getter, default: u64| {
+            if multi_config.arg_matches().occurrences_of(name) > 0 {
+                return Ok(value_m!(multi_config, name, u64).expect("Value disappeared"));
+            }
+            match persistent_config_opt.map(getter) {
+                Some(Ok(Some(value))) => Ok(value),
+                Some(Ok(None)) | None => Ok(default),
+                Some(Err(pce)) => Err(pce.into_configurator_error(name)),
+            }
+        };
+        Ok(LogRotationConfig {
+            size_mb: setting(
+                "log-rotation-size",
+                |pc| pc.log_rotation_size_mb(),
+                DEFAULT_LOG_ROTATION_SIZE_MB,
+            )?,
+            age_hours: setting(
+                "log-rotation-age",
+                |pc| pc.log_rotation_age_hours(),
+                DEFAULT_LOG_ROTATION_AGE_HOURS,
+            )?,
+            retention: setting(
+                "log-retention",
+                |pc| pc.log_retention(),
+                DEFAULT_LOG_RETENTION,
+            )?,
+        })
+    }
+
+    // The clandestine key is kept in the database only when it's supposed to outlive a run
+    fn key_rotation_config<'a>(
+        multi_config: &MultiConfig,
+        streams: &mut StdStreams<'_>,
+        config: &mut BootstrapperConfig,
+        persistent_config_opt: Option<&mut (dyn PersistentConfiguration + 'a)>,
+    ) -> Result<Option<KeyRotationConfig>, ConfiguratorError> {
+        let lifetime_days = match value_m!(multi_config, "clandestine-key-lifetime", u64) {
+            Some(days) => days,
+            None => return Ok(None),
+        };
+        let db_password_opt = match persistent_config_opt {
+            Some(persistent_config) => {
+                get_db_password(multi_config, streams, config, persistent_config)?
+            }
+            None => None,
+        };
+        if db_password_opt.is_none() {
+            return Err(ConfiguratorError::new(vec![ParamError::with_code(
+                "db-password",
+                ParamErrorCode::Required,
+                "Must be specified with --clandestine-key-lifetime, so that the key can be kept in the database",
+            )]));
+        }
+        let overlap_hours = value_m!(multi_config, "clandestine-key-overlap", u64)
+            .unwrap_or(DEFAULT_KEY_OVERLAP_HOURS);
+        Ok(Some(KeyRotationConfig {
+            lifetime: Duration::from_secs(lifetime_days * 86400),
+            overlap: Duration::from_secs(overlap_hours * 3600),
+        }))
+    }
+
     pub fn configure_database(
         config: &BootstrapperConfig,
         persistent_config: &mut (dyn PersistentConfiguration),
@@ -348,7 +782,35 @@ pub mod standard {
             }
         }
         match persistent_config.earning_wallet_address() {
-            Ok(Some(_)) => (),
+            // A local proxy never looked for the earning wallet, so it leaves it alone
+            _ if config.local_proxy => {}
+            Ok(Some(address))
+                if address.to_lowercase() == config.earning_wallet.to_string().to_lowercase() => {}
+            // get_earning_wallet_from_address() only lets a different wallet through if the
+            // change was asked for
+            Ok(Some(_)) => {
+                let effective_block = match persistent_config.start_block() {
+                    Ok(Some(block)) => block,
+                    Ok(None) => 0,
+                    Err(pce) => return Err(pce.into_configurator_error("earning-wallet")),
+                };
+                let signature = match &config.earning_wallet_change_signature_opt {
+                    Some(signature) => signature,
+                    None => {
+                        return Err(ConfiguratorError::required(
+                            "earning-wallet-change-signature",
+                            "Cannot change earning wallets without the old wallet's signature",
+                        ))
+                    }
+                };
+                if let Err(pce) = persistent_config.change_earning_wallet_address(
+                    &config.earning_wallet.to_string(),
+                    effective_block,
+                    signature,
+                ) {
+                    return Err(pce.into_configurator_error("earning-wallet"));
+                }
+            }
             Ok(None) => {
                 if let Err(pce) =
                     persistent_config.set_earning_wallet_address(&config.earning_wallet.to_string())
@@ -363,6 +825,17 @@ pub mod standard {
         {
             return Err(pce.into_configurator_error("gas-price"));
         }
+        if let Err(pce) = persistent_config.set_log_rotation_size_mb(config.log_rotation.size_mb) {
+            return Err(pce.into_configurator_error("log-rotation-size"));
+        }
+        if let Err(pce) =
+            persistent_config.set_log_rotation_age_hours(config.log_rotation.age_hours)
+        {
+            return Err(pce.into_configurator_error("log-rotation-age"));
+        }
+        if let Err(pce) = persistent_config.set_log_retention(config.log_rotation.retention) {
+            return Err(pce.into_configurator_error("log-retention"));
+        }
         let consuming_wallet_derivation_path_opt =
             match persistent_config.consuming_wallet_derivation_path() {
                 Ok(path_opt) => path_opt,
@@ -402,7 +875,7 @@ pub mod standard {
         config: &mut BootstrapperConfig,
     ) -> Result<(), ConfiguratorError> {
         let earning_wallet_opt =
-            standard::get_earning_wallet_from_address(multi_config, persistent_config)?;
+            standard::get_earning_wallet_from_address(multi_config, persistent_config, config)?;
         let mut consuming_wallet_opt =
             standard::get_consuming_wallet_from_private_key(multi_config, persistent_config)?;
         let mnemonic_seed_exists = match persistent_config.mnemonic_seed_exists() {
@@ -410,7 +883,7 @@ pub mod standard {
             Err(pce) => return Err(pce.into_configurator_error("seed")),
         };
         if earning_wallet_opt.is_some() && consuming_wallet_opt.is_some() && mnemonic_seed_exists {
-            return Err(ConfiguratorError::required("consuming-private-key", "Cannot use --consuming-private-key and --earning-wallet when database contains mnemonic seed"));
+            return Err(ConfiguratorError::conflict(&["consuming-private-key", "earning-wallet"], "Cannot use --consuming-private-key and --earning-wallet when database contains mnemonic seed"));
         }
 
         if (earning_wallet_opt.is_none() || consuming_wallet_opt.is_none()) && mnemonic_seed_exists
@@ -447,8 +920,14 @@ pub mod standard {
         unprivileged_config: &mut BootstrapperConfig,
     ) -> Result<NeighborhoodConfig, ConfiguratorError> {
         let neighbor_configs: Vec<NodeDescriptor> = {
-            match convert_ci_configs(multi_config)? {
-                Some(configs) => configs,
+            match convert_signed_ci_configs(multi_config)? {
+                Some(signed_configs) => {
+                    note_neighbor_signers(&signed_configs, streams, unprivileged_config);
+                    signed_configs
+                        .into_iter()
+                        .map(|(descriptor, _)| descriptor)
+                        .collect()
+                }
                 None => match persistent_config_opt {
                     Some(persistent_config) => get_past_neighbors(
                         multi_config,
@@ -466,10 +945,50 @@ pub mod standard {
         }
     }
 
-    #[allow(clippy::collapsible_if)]
+    // A signature only proves that some wallet signed the descriptor, so the operator is told
+    // which one, and the Neighborhood refuses the Node if it turns out to earn into another
+    fn note_neighbor_signers(
+        signed_configs: &[(NodeDescriptor, Option<Wallet>)],
+        streams: &mut StdStreams,
+        unprivileged_config: &mut BootstrapperConfig,
+    ) {
+        signed_configs
+            .iter()
+            .for_each(|(descriptor, signer_opt)| {
+                if let Some(signer) = signer_opt {
+                    flushed_write(
+                        streams.stdout,
+                        &format!(
+                            "Neighbor {} is vouched for by earning wallet {}; make sure that's the operator you expect\n",
+                            descriptor.encryption_public_key, signer
+                        ),
+                    );
+                    unprivileged_config
+                        .neighbor_signers
+                        .insert(descriptor.encryption_public_key.clone(), signer.clone());
+                }
+            });
+    }
+
     pub fn convert_ci_configs(
         multi_config: &MultiConfig,
     ) -> Result<Option<Vec<NodeDescriptor>>, ConfiguratorError> {
+        Ok(
+            convert_signed_ci_configs(multi_config)?.map(|signed_configs| {
+                signed_configs
+                    .into_iter()
+                    .map(|(descriptor, _)| descriptor)
+                    .collect()
+            }),
+        )
+    }
+
+    // Each descriptor in --neighbors, with the earning wallet that signed it, if any
+    #[allow(clippy::collapsible_if)]
+    #[allow(clippy::type_complexity)]
+    pub fn convert_signed_ci_configs(
+        multi_config: &MultiConfig,
+    ) -> Result<Option<Vec<(NodeDescriptor, Option<Wallet>)>>, ConfiguratorError> {
         match value_m!(multi_config, "neighbors", String) {
             None => Ok(None),
             Some(joined_configs) => {
@@ -492,10 +1011,13 @@ pub mod standard {
                     let results = cli_configs
                         .into_iter()
                         .map(
-                            |s| match NodeDescriptor::from_str(dummy_cryptde.as_ref(), &s) {
-                                Ok(nd) => if chain_name == DEFAULT_CHAIN_NAME {
+                            |s| match verify_signed_descriptor(&s).and_then(|(descriptor, signer_opt)| {
+                                NodeDescriptor::from_str(dummy_cryptde.as_ref(), &descriptor)
+                                    .map(|nd| (nd, signer_opt))
+                            }) {
+                                Ok((nd, signer_opt)) => if chain_name == DEFAULT_CHAIN_NAME {
                                     if nd.mainnet {
-                                        Ok(nd)
+                                        Ok((nd, signer_opt))
                                     }
                                     else {
                                         Err(ParamError::new("neighbors", "Mainnet node descriptors use '@', not ':', as the first delimiter"))
@@ -506,7 +1028,7 @@ pub mod standard {
                                         Err(ParamError::new("neighbors", &format!("Mainnet node descriptor uses '@', but chain configured for '{}'", chain_name)))
                                     }
                                     else {
-                                        Ok(nd)
+                                        Ok((nd, signer_opt))
                                     }
                                 },
                                 Err(e) => Err(ParamError::new("neighbors", &e)),
@@ -527,7 +1049,7 @@ pub mod standard {
                                 .into_iter()
                                 .filter(|result| result.is_ok())
                                 .map(|result| result.expect("Error materialized"))
-                                .collect::<Vec<NodeDescriptor>>(),
+                                .collect::<Vec<(NodeDescriptor, Option<Wallet>)>>(),
                         ))
                     } else {
                         Err(ConfiguratorError::new(errors))
@@ -582,7 +1104,7 @@ pub mod standard {
             }
             Some(ref s) if s == "originate-only" => {
                 if neighbor_configs.is_empty() {
-                    Err(ConfiguratorError::required("neighborhood-mode", "Node cannot run as --neighborhood-mode originate-only without --neighbors specified"))
+                    Err(ConfiguratorError::conflict(&["neighborhood-mode", "neighbors"], "Node cannot run as --neighborhood-mode originate-only without --neighbors specified"))
                 } else {
                     Ok(NeighborhoodMode::OriginateOnly(
                         neighbor_configs,
@@ -592,17 +1114,17 @@ pub mod standard {
             }
             Some(ref s) if s == "consume-only" => {
                 if neighbor_configs.is_empty() {
-                    Err(ConfiguratorError::required("neighborhood-mode", "Node cannot run as --neighborhood-mode consume-only without --neighbors specified"))
+                    Err(ConfiguratorError::conflict(&["neighborhood-mode", "neighbors"], "Node cannot run as --neighborhood-mode consume-only without --neighbors specified"))
                 } else {
                     Ok(NeighborhoodMode::ConsumeOnly(neighbor_configs))
                 }
             }
             Some(ref s) if s == "zero-hop" => {
                 if !neighbor_configs.is_empty() {
-                    Err(ConfiguratorError::required("neighborhood-mode", "Node cannot run as --neighborhood-mode zero-hop if --neighbors is specified"))
+                    Err(ConfiguratorError::conflict(&["neighborhood-mode", "neighbors"], "Node cannot run as --neighborhood-mode zero-hop if --neighbors is specified"))
                 } else if value_m!(multi_config, "ip", IpAddr).is_some() {
-                    Err(ConfiguratorError::required(
-                        "neighborhood-mode",
+                    Err(ConfiguratorError::conflict(
+                        &["neighborhood-mode", "ip"],
                         "Node cannot run as --neighborhood-mode zero-hop if --ip is specified",
                     ))
                 } else {
@@ -625,8 +1147,8 @@ pub mod standard {
         let ip = match value_m!(multi_config, "ip", IpAddr) {
             Some(ip) => ip,
             None => {
-                return Err(ConfiguratorError::required(
-                    "neighborhood-mode",
+                return Err(ConfiguratorError::conflict(
+                    &["neighborhood-mode", "ip"],
                     "Node cannot run as --neighborhood-mode standard without --ip specified",
                 ))
             }
@@ -641,6 +1163,7 @@ pub mod standard {
     fn get_earning_wallet_from_address(
         multi_config: &MultiConfig,
         persistent_config: &dyn PersistentConfiguration,
+        config: &mut BootstrapperConfig,
     ) -> Result<Option<Wallet>, ConfiguratorError> {
         let earning_wallet_from_command_line_opt = value_m!(multi_config, "earning-wallet", String);
         let earning_wallet_from_database_opt = match persistent_config.earning_wallet_from_address()
@@ -659,18 +1182,55 @@ pub mod standard {
             )),
             (None, Some(wallet)) => Ok(Some(wallet)),
             (Some(address), Some(wallet)) => {
+                let change_allowed = match value_m!(multi_config, "earning-wallet-change", String) {
+                    Some(ref value) => value.eq_ignore_ascii_case("on"),
+                    None => false,
+                };
                 if wallet.to_string().to_lowercase() == address.to_lowercase() {
                     Ok(Some(wallet))
+                } else if change_allowed {
+                    let new_wallet = Wallet::from_str(&address)
+                        .expect("--earning-wallet not properly constrained by clap");
+                    config.earning_wallet_change_signature_opt = Some(
+                        get_earning_wallet_change_signature(multi_config, &wallet, &new_wallet)?,
+                    );
+                    Ok(Some(new_wallet))
                 } else {
                     Err(ConfiguratorError::required(
                         "earning-wallet",
-                        &format!("Cannot change to an address ({}) different from that previously set ({})", address.to_lowercase(), wallet.to_string().to_lowercase())
+                        &format!("Cannot change to an address ({}) different from that previously set ({}) without --earning-wallet-change on", address.to_lowercase(), wallet.to_string().to_lowercase())
                     ))
                 }
             }
         }
     }
 
+    // Only the old wallet's owner may send what it's owed somewhere else
+    fn get_earning_wallet_change_signature(
+        multi_config: &MultiConfig,
+        previous_wallet: &Wallet,
+        new_wallet: &Wallet,
+    ) -> Result<String, ConfiguratorError> {
+        let message = WalletChange::message(previous_wallet, new_wallet);
+        match value_m!(multi_config, "earning-wallet-change-signature", String) {
+            Some(signature) if is_signed_by(&message, previous_wallet, &signature) => Ok(signature),
+            Some(signature) => Err(ConfiguratorError::required(
+                "earning-wallet-change-signature",
+                &format!(
+                    "{} is not {}'s signature of '{}'",
+                    signature, previous_wallet, message
+                ),
+            )),
+            None => Err(ConfiguratorError::required(
+                "earning-wallet-change-signature",
+                &format!(
+                    "To change earning wallets, sign '{}' with {} and supply the signature",
+                    message, previous_wallet
+                ),
+            )),
+        }
+    }
+
     fn get_consuming_wallet_opt_from_derivation_path(
         persistent_config: &dyn PersistentConfiguration,
         db_password: &str,
@@ -679,23 +1239,17 @@ pub mod standard {
             Ok(None) => Ok(None),
             Ok(Some(derivation_path)) => match persistent_config.mnemonic_seed(db_password) {
                 Ok(None) => Ok(None),
-                Ok(Some(mnemonic_seed)) => {
-                    let keypair =
-                        Bip32ECKeyPair::from_raw(mnemonic_seed.as_ref(), &derivation_path)
-                            .unwrap_or_else(|_| {
-                                panic!(
-                            "Error making keypair from mnemonic seed and derivation path {}",
-                            derivation_path
-                        )
-                            });
-                    Ok(Some(Wallet::from(keypair)))
-                }
+                Ok(Some(mnemonic_seed)) => Ok(Some(derive_wallet(
+                    &mnemonic_seed,
+                    &derivation_path,
+                    "consuming-wallet",
+                )?)),
                 Err(e) => match e {
                     PersistentConfigError::PasswordError => Err(ConfiguratorError::required(
                         "db-password",
                         "Incorrect password for retrieving mnemonic seed",
                     )),
-                    e => panic!("{:?}", e),
+                    e => Err(e.into_configurator_error("consuming-wallet")),
                 },
             },
             Err(e) => Err(e.into_configurator_error("consuming-wallet")),
@@ -755,6 +1309,7 @@ pub mod standard {
             (None, false) => None,
             (None, true) => match request_existing_db_password(
                 streams,
+                prompt_backend(multi_config).as_ref(),
                 Some("Decrypt information from previous runs"),
                 "Enter password: ",
                 persistent_config,
@@ -779,7 +1334,22 @@ pub mod standard {
                 Ok(_) => Ok(true),
                 Err(e) => Err(e.into_configurator_error("db-password")),
             },
-            Ok(false) => Ok(false),
+            Ok(false) => {
+                match check_password_within_budget(
+                    persistent_config,
+                    db_password,
+                    SystemTime::now(),
+                ) {
+                    Ok(true) => (),
+                    // Left for whatever needs the password to refuse it
+                    Ok(false) => return Ok(false),
+                    Err(e) => return Err(e.into_configurator_error("db-password")),
+                }
+                match persistent_config.upgrade_password_protection(db_password) {
+                    Ok(_) => Ok(false),
+                    Err(e) => Err(e.into_configurator_error("db-password")),
+                }
+            }
             Err(e) => Err(e.into_configurator_error("db-password")),
         }
     }
@@ -789,6 +1359,7 @@ pub mod standard {
         use super::*;
         use crate::db_config::persistent_configuration::PersistentConfigError;
         use crate::db_config::persistent_configuration::PersistentConfigError::NotPresent;
+        use crate::sub_lib::descriptor_signature::sign_message;
         use crate::sub_lib::utils::make_new_test_multi_config;
         use crate::test_utils::persistent_configuration_mock::PersistentConfigurationMock;
         use crate::test_utils::{
@@ -833,7 +1404,7 @@ pub mod standard {
             .err()
             .unwrap();
 
-            assert_eq! (result, ConfiguratorError::required("consuming-private-key", "Cannot use --consuming-private-key and --earning-wallet when database contains mnemonic seed"))
+            assert_eq! (result, ConfiguratorError::conflict(&["consuming-private-key", "earning-wallet"], "Cannot use --consuming-private-key and --earning-wallet when database contains mnemonic seed"))
         }
 
         #[test]
@@ -853,6 +1424,7 @@ pub mod standard {
             let mut persistent_config = PersistentConfigurationMock::new()
                 .earning_wallet_from_address_result (Ok (None))
                 .check_password_result(Ok(false))
+                .check_password_result(Ok(true))
                 .mnemonic_seed_exists_result (Ok(true))
                 .consuming_wallet_derivation_path_result(Ok(Some("path".to_string())))
                 .consuming_wallet_public_key_result(Ok(Some(PlainData::from_str ("c2a4c3969a1acfd0a67f8881a894f0db3b36f7f1dde0b053b988bf7cff325f6c3129d83b9d6eeb205e3274193b033f106bea8bbc7bdd5f85589070effccbf55e").unwrap())));
@@ -873,6 +1445,7 @@ pub mod standard {
         #[test]
         fn configure_database_handles_error_setting_consuming_wallet_public_key() {
             let mut config = BootstrapperConfig::new();
+            config.earning_wallet = Wallet::new("0x0123456789012345678901234567890123456789");
             config.clandestine_port_opt = None;
             let mut persistent_config = PersistentConfigurationMock::new()
                 .earning_wallet_address_result(Ok(Some(
@@ -912,6 +1485,7 @@ pub mod standard {
         #[test]
         fn configure_database_handles_error_setting_consuming_wallet_derivation_path() {
             let mut config = BootstrapperConfig::new();
+            config.earning_wallet = Wallet::new("0x0123456789012345678901234567890123456789");
             config.clandestine_port_opt = None;
             let mut persistent_config = PersistentConfigurationMock::new()
                 .set_clandestine_port_result(Ok(()))
@@ -972,6 +1546,7 @@ pub mod standard {
         #[test]
         fn configure_database_handles_error_during_setting_gas_price() {
             let mut config = BootstrapperConfig::new();
+            config.earning_wallet = Wallet::new("0x0123456789012345678901234567890123456789");
             config.clandestine_port_opt = None;
             let mut persistent_config = PersistentConfigurationMock::new()
                 .earning_wallet_address_result(Ok(Some(
@@ -987,6 +1562,63 @@ pub mod standard {
             )
         }
 
+        #[test]
+        fn configure_database_persists_log_rotation() {
+            let mut config = BootstrapperConfig::new();
+            config.earning_wallet = Wallet::new("0x0123456789012345678901234567890123456789");
+            config.log_rotation = LogRotationConfig {
+                size_mb: 20,
+                age_hours: 24,
+                retention: 7,
+            };
+            let set_log_rotation_size_mb_params_arc = Arc::new(Mutex::new(vec![]));
+            let set_log_rotation_age_hours_params_arc = Arc::new(Mutex::new(vec![]));
+            let set_log_retention_params_arc = Arc::new(Mutex::new(vec![]));
+            let mut persistent_config = PersistentConfigurationMock::new()
+                .earning_wallet_address_result(Ok(Some(
+                    "0x0123456789012345678901234567890123456789".to_string(),
+                )))
+                .set_gas_price_result(Ok(()))
+                .set_log_rotation_size_mb_params(&set_log_rotation_size_mb_params_arc)
+                .set_log_rotation_age_hours_params(&set_log_rotation_age_hours_params_arc)
+                .set_log_retention_params(&set_log_retention_params_arc)
+                .consuming_wallet_public_key_result(Ok(None))
+                .consuming_wallet_derivation_path_result(Ok(None));
+
+            let result = configure_database(&config, &mut persistent_config);
+
+            assert_eq!(result, Ok(()));
+            let set_log_rotation_size_mb_params =
+                set_log_rotation_size_mb_params_arc.lock().unwrap();
+            assert_eq!(*set_log_rotation_size_mb_params, vec![20]);
+            let set_log_rotation_age_hours_params =
+                set_log_rotation_age_hours_params_arc.lock().unwrap();
+            assert_eq!(*set_log_rotation_age_hours_params, vec![24]);
+            let set_log_retention_params = set_log_retention_params_arc.lock().unwrap();
+            assert_eq!(*set_log_retention_params, vec![7]);
+        }
+
+        #[test]
+        fn configure_database_handles_error_during_setting_log_retention() {
+            let mut config = BootstrapperConfig::new();
+            config.earning_wallet = Wallet::new("0x0123456789012345678901234567890123456789");
+            config.clandestine_port_opt = None;
+            let mut persistent_config = PersistentConfigurationMock::new()
+                .earning_wallet_address_result(Ok(Some(
+                    "0x0123456789012345678901234567890123456789".to_string(),
+                )))
+                .set_gas_price_result(Ok(()))
+                .set_log_retention_result(Err(PersistentConfigError::TransactionError));
+
+            let result = configure_database(&config, &mut persistent_config);
+
+            assert_eq!(
+                result,
+                Err(PersistentConfigError::TransactionError
+                    .into_configurator_error("log-retention"))
+            )
+        }
+
         #[test]
         fn configure_database_handles_error_setting_earning_wallet_address() {
             let mut config = BootstrapperConfig::new();
@@ -1017,7 +1649,11 @@ pub mod standard {
             let persistent_config = PersistentConfigurationMock::new()
                 .earning_wallet_from_address_result(Err(PersistentConfigError::NotPresent));
 
-            let result = get_earning_wallet_from_address(&multi_config, &persistent_config);
+            let result = get_earning_wallet_from_address(
+                &multi_config,
+                &persistent_config,
+                &mut BootstrapperConfig::new(),
+            );
 
             assert_eq!(
                 result,
@@ -1159,79 +1795,335 @@ pub mod standard {
                     "0x9876543210987654321098765432109876543210",
                 ))));
 
-            let result =
-                standard::get_earning_wallet_from_address(&multi_config, &persistent_config)
-                    .err()
-                    .unwrap();
+            let result = standard::get_earning_wallet_from_address(
+                &multi_config,
+                &persistent_config,
+                &mut BootstrapperConfig::new(),
+            )
+            .err()
+            .unwrap();
+
+            assert_eq! (result, ConfiguratorError::required("earning-wallet", "Cannot change to an address (0x0123456789012345678901234567890123456789) different from that previously set (0x9876543210987654321098765432109876543210) without --earning-wallet-change on"))
+        }
 
-            assert_eq! (result, ConfiguratorError::required("earning-wallet", "Cannot change to an address (0x0123456789012345678901234567890123456789) different from that previously set (0x9876543210987654321098765432109876543210)"))
+        fn earning_wallet_change_multi_config(signature_opt: Option<&str>) -> MultiConfig<'_> {
+            let args = ArgsBuilder::new()
+                .param(
+                    "--earning-wallet",
+                    "0x0123456789012345678901234567890123456789",
+                )
+                .param("--earning-wallet-change", "on");
+            let args = match signature_opt {
+                Some(signature) => args.param("--earning-wallet-change-signature", signature),
+                None => args,
+            };
+            let vcls: Vec<Box<dyn VirtualCommandLine>> =
+                vec![Box::new(CommandLineVcl::new(args.into()))];
+            make_new_test_multi_config(&app(), vcls).unwrap()
         }
 
         #[test]
-        fn get_consuming_wallet_opt_from_derivation_path_handles_bad_password() {
+        fn get_earning_wallet_from_address_allows_wallet_change_signed_by_the_old_wallet() {
             running_test();
+            let previous_wallet = make_paying_wallet(b"previous earning wallet");
+            let new_wallet = Wallet::new("0x0123456789012345678901234567890123456789");
+            let signature = sign_message(
+                &WalletChange::message(&previous_wallet, &new_wallet),
+                &previous_wallet,
+            )
+            .unwrap();
+            let multi_config = earning_wallet_change_multi_config(Some(&signature));
             let persistent_config = PersistentConfigurationMock::new()
-                .consuming_wallet_derivation_path_result(Ok(Some("m/44'/60'/1'/2/3".to_string())))
-                .mnemonic_seed_result(Err(PersistentConfigError::PasswordError));
+                .earning_wallet_from_address_result(Ok(Some(previous_wallet.as_address_wallet())));
+            let mut config = BootstrapperConfig::new();
 
-            let result = standard::get_consuming_wallet_opt_from_derivation_path(
+            let result = standard::get_earning_wallet_from_address(
+                &multi_config,
                 &persistent_config,
-                "bad password",
-            )
-            .err()
-            .unwrap();
+                &mut config,
+            );
 
-            assert_eq!(
-                result,
-                ConfiguratorError::required(
-                    "db-password",
-                    "Incorrect password for retrieving mnemonic seed"
-                )
-            )
+            assert_eq!(result, Ok(Some(new_wallet)));
+            assert_eq!(config.earning_wallet_change_signature_opt, Some(signature));
         }
 
         #[test]
-        fn get_consuming_wallet_from_private_key_handles_mismatches() {
+        fn get_earning_wallet_from_address_refuses_wallet_change_not_signed_by_the_old_wallet() {
             running_test();
-            let args = ArgsBuilder::new().param(
-                "--consuming-private-key",
-                "00112233445566778899AABBCCDDEEFF00112233445566778899AABBCCDDEEFF",
-            );
-            let vcls: Vec<Box<dyn VirtualCommandLine>> =
-                vec![Box::new(CommandLineVcl::new(args.into()))];
-            let multi_config = make_new_test_multi_config(&app(), vcls).unwrap();
-            let persistent_config = PersistentConfigurationMock::new()
-                .consuming_wallet_public_key_result(Ok(Some(
-                    PlainData::from_str("0123456789012345678901234567890123456789").unwrap(),
-                )));
+            let previous_wallet = Wallet::new("0x9876543210987654321098765432109876543210");
+            let new_wallet = Wallet::new("0x0123456789012345678901234567890123456789");
+            let message = WalletChange::message(&previous_wallet, &new_wallet);
+            let forged_signature = sign_message(&message, &make_paying_wallet(b"thief")).unwrap();
+            let persistent_config = || {
+                PersistentConfigurationMock::new()
+                    .earning_wallet_from_address_result(Ok(Some(previous_wallet.clone())))
+            };
 
-            let result =
-                standard::get_consuming_wallet_from_private_key(&multi_config, &persistent_config)
-                    .err()
-                    .unwrap();
+            let forged = standard::get_earning_wallet_from_address(
+                &earning_wallet_change_multi_config(Some(&forged_signature)),
+                &persistent_config(),
+                &mut BootstrapperConfig::new(),
+            );
+            let unsigned = standard::get_earning_wallet_from_address(
+                &earning_wallet_change_multi_config(None),
+                &persistent_config(),
+                &mut BootstrapperConfig::new(),
+            );
 
             assert_eq!(
-                result,
-                ConfiguratorError::required(
-                    "consuming-private-key",
-                    "Not the private key of the consuming wallet you have used in the past"
-                )
-            )
+                forged,
+                Err(ConfiguratorError::required(
+                    "earning-wallet-change-signature",
+                    &format!(
+                        "{} is not {}'s signature of '{}'",
+                        forged_signature, previous_wallet, message
+                    )
+                ))
+            );
+            assert_eq!(
+                unsigned,
+                Err(ConfiguratorError::required(
+                    "earning-wallet-change-signature",
+                    &format!(
+                        "To change earning wallets, sign '{}' with {} and supply the signature",
+                        message, previous_wallet
+                    )
+                ))
+            );
         }
 
         #[test]
-        fn set_db_password_at_first_mention_handles_existing_password() {
-            let check_password_params_arc = Arc::new(Mutex::new(vec![]));
-            let mut persistent_config = make_default_persistent_configuration()
-                .check_password_params(&check_password_params_arc)
-                .check_password_result(Ok(false));
-
-            let result =
-                standard::set_db_password_at_first_mention("password", &mut persistent_config);
+        fn configure_database_changes_earning_wallet_address_as_of_the_start_block() {
+            let mut config = BootstrapperConfig::new();
+            config.clandestine_port_opt = None;
+            config.earning_wallet = Wallet::new("0x0123456789012345678901234567890123456789");
+            config.earning_wallet_change_signature_opt = Some("0x0102".to_string());
+            let change_earning_wallet_address_params_arc = Arc::new(Mutex::new(vec![]));
+            let mut persistent_config = PersistentConfigurationMock::new()
+                .earning_wallet_address_result(Ok(Some(
+                    "0x9876543210987654321098765432109876543210".to_string(),
+                )))
+                .start_block_result(Ok(Some(1234)))
+                .change_earning_wallet_address_params(&change_earning_wallet_address_params_arc)
+                .change_earning_wallet_address_result(Ok(()))
+                .set_gas_price_result(Ok(()))
+                .consuming_wallet_derivation_path_result(Ok(None))
+                .consuming_wallet_public_key_result(Ok(None));
+
+            let result = configure_database(&config, &mut persistent_config);
+
+            assert_eq!(result, Ok(()));
+            let change_earning_wallet_address_params =
+                change_earning_wallet_address_params_arc.lock().unwrap();
+            assert_eq!(
+                *change_earning_wallet_address_params,
+                vec![(
+                    "0x0123456789012345678901234567890123456789".to_string(),
+                    1234,
+                    "0x0102".to_string()
+                )]
+            );
+        }
+
+        #[test]
+        fn configure_database_refuses_to_change_earning_wallet_without_the_old_wallets_signature() {
+            let mut config = BootstrapperConfig::new();
+            config.earning_wallet = Wallet::new("0x0123456789012345678901234567890123456789");
+            let change_earning_wallet_address_params_arc = Arc::new(Mutex::new(vec![]));
+            let mut persistent_config = PersistentConfigurationMock::new()
+                .earning_wallet_address_result(Ok(Some(
+                    "0x9876543210987654321098765432109876543210".to_string(),
+                )))
+                .start_block_result(Ok(Some(1234)))
+                .change_earning_wallet_address_params(&change_earning_wallet_address_params_arc);
+
+            let result = configure_database(&config, &mut persistent_config);
+
+            assert_eq!(
+                result,
+                Err(ConfiguratorError::required(
+                    "earning-wallet-change-signature",
+                    "Cannot change earning wallets without the old wallet's signature"
+                ))
+            );
+            assert!(change_earning_wallet_address_params_arc
+                .lock()
+                .unwrap()
+                .is_empty());
+        }
+
+        #[test]
+        fn configure_database_leaves_the_earning_wallet_alone_for_a_local_proxy() {
+            let mut config = BootstrapperConfig::new();
+            config.local_proxy = true;
+            let change_earning_wallet_address_params_arc = Arc::new(Mutex::new(vec![]));
+            let set_earning_wallet_address_params_arc = Arc::new(Mutex::new(vec![]));
+            let mut persistent_config = PersistentConfigurationMock::new()
+                .earning_wallet_address_result(Ok(Some(
+                    "0x9876543210987654321098765432109876543210".to_string(),
+                )))
+                .change_earning_wallet_address_params(&change_earning_wallet_address_params_arc)
+                .set_earning_wallet_address_params(&set_earning_wallet_address_params_arc)
+                .set_gas_price_result(Ok(()))
+                .consuming_wallet_derivation_path_result(Ok(None))
+                .consuming_wallet_public_key_result(Ok(None));
+
+            let result = configure_database(&config, &mut persistent_config);
+
+            assert_eq!(result, Ok(()));
+            assert!(change_earning_wallet_address_params_arc
+                .lock()
+                .unwrap()
+                .is_empty());
+            assert!(set_earning_wallet_address_params_arc
+                .lock()
+                .unwrap()
+                .is_empty());
+        }
+
+        #[test]
+        fn get_consuming_wallet_opt_from_derivation_path_handles_bad_password() {
+            running_test();
+            let persistent_config = PersistentConfigurationMock::new()
+                .consuming_wallet_derivation_path_result(Ok(Some("m/44'/60'/1'/2/3".to_string())))
+                .mnemonic_seed_result(Err(PersistentConfigError::PasswordError));
+
+            let result = standard::get_consuming_wallet_opt_from_derivation_path(
+                &persistent_config,
+                "bad password",
+            )
+            .err()
+            .unwrap();
+
+            assert_eq!(
+                result,
+                ConfiguratorError::required(
+                    "db-password",
+                    "Incorrect password for retrieving mnemonic seed"
+                )
+            )
+        }
+
+        #[test]
+        fn get_consuming_wallet_from_private_key_handles_mismatches() {
+            running_test();
+            let args = ArgsBuilder::new().param(
+                "--consuming-private-key",
+                "00112233445566778899AABBCCDDEEFF00112233445566778899AABBCCDDEEFF",
+            );
+            let vcls: Vec<Box<dyn VirtualCommandLine>> =
+                vec![Box::new(CommandLineVcl::new(args.into()))];
+            let multi_config = make_new_test_multi_config(&app(), vcls).unwrap();
+            let persistent_config = PersistentConfigurationMock::new()
+                .consuming_wallet_public_key_result(Ok(Some(
+                    PlainData::from_str("0123456789012345678901234567890123456789").unwrap(),
+                )));
+
+            let result =
+                standard::get_consuming_wallet_from_private_key(&multi_config, &persistent_config)
+                    .err()
+                    .unwrap();
+
+            assert_eq!(
+                result,
+                ConfiguratorError::required(
+                    "consuming-private-key",
+                    "Not the private key of the consuming wallet you have used in the past"
+                )
+            )
+        }
+
+        #[test]
+        fn set_db_password_at_first_mention_handles_existing_password() {
+            let check_password_params_arc = Arc::new(Mutex::new(vec![]));
+            let upgrade_password_protection_params_arc = Arc::new(Mutex::new(vec![]));
+            let mut persistent_config = make_default_persistent_configuration()
+                .check_password_params(&check_password_params_arc)
+                .check_password_result(Ok(false))
+                .check_password_result(Ok(true))
+                .upgrade_password_protection_params(&upgrade_password_protection_params_arc)
+                .upgrade_password_protection_result(Ok(true));
+
+            let result =
+                standard::set_db_password_at_first_mention("password", &mut persistent_config);
 
             assert_eq!(result, Ok(false));
             let check_password_params = check_password_params_arc.lock().unwrap();
-            assert_eq!(*check_password_params, vec![None])
+            assert_eq!(
+                *check_password_params,
+                vec![None, Some("password".to_string())]
+            );
+            let upgrade_password_protection_params =
+                upgrade_password_protection_params_arc.lock().unwrap();
+            assert_eq!(
+                *upgrade_password_protection_params,
+                vec!["password".to_string()]
+            )
+        }
+
+        #[test]
+        fn set_db_password_at_first_mention_handles_upgrade_error() {
+            let mut persistent_config = make_default_persistent_configuration()
+                .check_password_result(Ok(false))
+                .check_password_result(Ok(true))
+                .upgrade_password_protection_result(Err(NotPresent));
+
+            let result =
+                standard::set_db_password_at_first_mention("password", &mut persistent_config);
+
+            assert_eq!(
+                result,
+                Err(NotPresent.into_configurator_error("db-password"))
+            );
+        }
+
+        #[test]
+        fn set_db_password_at_first_mention_counts_an_incorrect_password_and_doesnt_upgrade() {
+            let set_db_password_failures_params_arc = Arc::new(Mutex::new(vec![]));
+            let upgrade_password_protection_params_arc = Arc::new(Mutex::new(vec![]));
+            let mut persistent_config = make_default_persistent_configuration()
+                .check_password_result(Ok(false))
+                .check_password_result(Ok(false))
+                .db_password_failures_result(Ok((1, 0)))
+                .set_db_password_failures_params(&set_db_password_failures_params_arc)
+                .upgrade_password_protection_params(&upgrade_password_protection_params_arc);
+
+            let result =
+                standard::set_db_password_at_first_mention("booga", &mut persistent_config);
+
+            assert_eq!(result, Ok(false));
+            let set_db_password_failures_params =
+                set_db_password_failures_params_arc.lock().unwrap();
+            assert_eq!(set_db_password_failures_params.len(), 1);
+            assert_eq!(set_db_password_failures_params[0].0, 2);
+            assert!(upgrade_password_protection_params_arc
+                .lock()
+                .unwrap()
+                .is_empty());
+        }
+
+        #[test]
+        fn set_db_password_at_first_mention_refuses_to_check_during_a_cooldown() {
+            let check_password_params_arc = Arc::new(Mutex::new(vec![]));
+            let mut persistent_config = make_default_persistent_configuration()
+                .check_password_params(&check_password_params_arc)
+                .check_password_result(Ok(false))
+                .db_password_failures_result(Ok((4, u64::MAX)));
+
+            let result =
+                standard::set_db_password_at_first_mention("password", &mut persistent_config);
+
+            let message = match result {
+                Err(ConfiguratorError { param_errors }) => param_errors[0].reason.clone(),
+                x => panic!("Expected a cooldown, got {:?}", x),
+            };
+            assert!(
+                message.starts_with("Too many incorrect passwords; try again in "),
+                "{}",
+                message
+            );
+            let check_password_params = check_password_params_arc.lock().unwrap();
+            assert_eq!(*check_password_params, vec![None]);
         }
 
         #[test]
@@ -1302,6 +2194,7 @@ mod tests {
     use crate::blockchain::blockchain_interface::{
         chain_id_from_name, chain_name_from_id, contract_address,
     };
+    use crate::blockchain::payment_adapter::{default_payment_token, PaymentToken};
     use crate::bootstrapper::RealUser;
     use crate::database::db_initializer::{DbInitializer, DbInitializerReal};
     use crate::db_config::config_dao::{ConfigDao, ConfigDaoReal};
@@ -1309,26 +2202,46 @@ mod tests {
     use crate::db_config::persistent_configuration::{
         PersistentConfigError, PersistentConfigurationReal,
     };
+    use crate::geo_db::GeoDbConfig;
+    use crate::neighborhood::route_search::DEFAULT_ROUTE_SEARCH_THREADS;
     use crate::node_configurator::RealDirsWrapper;
     use crate::sub_lib::accountant::DEFAULT_EARNING_WALLET;
+    use crate::sub_lib::clock_skew::DEFAULT_CLOCK_SKEW_TOLERANCE_SECS;
     use crate::sub_lib::cryptde::{CryptDE, PlainData, PublicKey};
     use crate::sub_lib::cryptde_null::CryptDENull;
     use crate::sub_lib::cryptde_real::CryptDEReal;
+    use crate::sub_lib::data_cap::DataCapConfig;
+    use crate::sub_lib::descriptor_signature::{
+        sign_descriptor, signed_descriptor, SIGNATURE_DELIMITER,
+    };
+    use crate::sub_lib::key_rotation::KeyRotationConfig;
+    use crate::sub_lib::log_rotation::LogRotationConfig;
     use crate::sub_lib::neighborhood::{
         NeighborhoodConfig, NeighborhoodMode, NodeDescriptor, DEFAULT_RATE_PACK,
     };
     use crate::sub_lib::node_addr::NodeAddr;
+    use crate::sub_lib::proxy_client::{DEFAULT_DNS_CACHE_SIZE, DEFAULT_DNS_LOOKUP_THREADS};
+    use crate::sub_lib::proxy_server::{
+        EncryptedSniFallback, MultipathMode, SocksConfig, SocksCredentials,
+    };
+    use crate::sub_lib::ui_gateway::{UiAuthConfig, UiTlsConfig};
     use crate::sub_lib::utils::make_new_test_multi_config;
     use crate::sub_lib::wallet::Wallet;
     use crate::test_utils;
     use crate::test_utils::make_default_persistent_configuration;
     use crate::test_utils::persistent_configuration_mock::PersistentConfigurationMock;
-    use crate::test_utils::{assert_string_contains, main_cryptde, ArgsBuilder};
+    use crate::test_utils::{
+        assert_string_contains, main_cryptde, make_paying_wallet, make_wallet, ArgsBuilder,
+    };
+    use crate::updater::UpdateCheckConfig;
     use masq_lib::constants::{DEFAULT_CHAIN_NAME, DEFAULT_GAS_PRICE, DEFAULT_UI_PORT};
+    use masq_lib::data_cap::DataCapRate;
+    use masq_lib::exit_policy::{ContentCategory, ExitPolicy};
     use masq_lib::multi_config::{
         CommandLineVcl, ConfigFileVcl, NameValueVclArg, VclArg, VirtualCommandLine,
     };
-    use masq_lib::shared_schema::{ConfiguratorError, ParamError};
+    use masq_lib::secure_dns::SecureDnsServer;
+    use masq_lib::shared_schema::{ConfiguratorError, ParamError, ParamErrorCode};
     use masq_lib::test_utils::environment_guard::{ClapGuard, EnvironmentGuard};
     use masq_lib::test_utils::fake_stream_holder::{ByteArrayWriter, FakeStreamHolder};
     use masq_lib::test_utils::utils::{
@@ -1344,6 +2257,7 @@ mod tests {
     use std::path::PathBuf;
     use std::str::FromStr;
     use std::sync::{Arc, Mutex};
+    use std::time::Duration;
 
     fn make_default_cli_params() -> ArgsBuilder {
         ArgsBuilder::new().param("--ip", "1.2.3.4")
@@ -1424,8 +2338,8 @@ mod tests {
 
         assert_eq!(
             result,
-            Err(ConfiguratorError::required(
-                "neighborhood-mode",
+            Err(ConfiguratorError::conflict(
+                &["neighborhood-mode", "ip"],
                 "Node cannot run as --neighborhood-mode standard without --ip specified"
             ))
         )
@@ -1491,7 +2405,7 @@ mod tests {
             &mut BootstrapperConfig::new(),
         );
 
-        assert_eq! (result, Err(ConfiguratorError::required("neighborhood-mode", "Node cannot run as --neighborhood-mode originate-only without --neighbors specified")))
+        assert_eq! (result, Err(ConfiguratorError::conflict(&["neighborhood-mode", "neighbors"], "Node cannot run as --neighborhood-mode originate-only without --neighbors specified")))
     }
 
     #[test]
@@ -1552,8 +2466,8 @@ mod tests {
 
         assert_eq!(
             result,
-            Err(ConfiguratorError::required(
-                "neighborhood-mode",
+            Err(ConfiguratorError::conflict(
+                &["neighborhood-mode", "neighbors"],
                 "Node cannot run as --neighborhood-mode consume-only without --neighbors specified"
             ))
         )
@@ -1610,8 +2524,8 @@ mod tests {
 
         assert_eq!(
             result,
-            Err(ConfiguratorError::required(
-                "neighborhood-mode",
+            Err(ConfiguratorError::conflict(
+                &["neighborhood-mode", "ip"],
                 "Node cannot run as --neighborhood-mode zero-hop if --ip is specified"
             ))
         )
@@ -1644,8 +2558,8 @@ mod tests {
 
         assert_eq!(
             result,
-            Err(ConfiguratorError::required(
-                "neighborhood-mode",
+            Err(ConfiguratorError::conflict(
+                &["neighborhood-mode", "neighbors"],
                 "Node cannot run as --neighborhood-mode zero-hop if --neighbors is specified"
             ))
         )
@@ -1793,6 +2707,103 @@ mod tests {
         );
     }
 
+    #[test]
+    fn convert_ci_configs_accepts_neighbors_signed_by_the_wallets_they_name() {
+        running_test();
+        let descriptor = "mhtjjdMt7Gyoebtb1yiK0hdaUx6j84noHdaAHeDR1S4@1.2.3.4:1234;2345";
+        let signed =
+            sign_descriptor(descriptor, &make_paying_wallet(b"neighbor operator")).unwrap();
+        let impostor = signed_descriptor(
+            descriptor,
+            &make_wallet("impostor"),
+            signed.rsplit(SIGNATURE_DELIMITER).next().unwrap(),
+        );
+        let parse = |neighbors: &str| {
+            let multi_config = make_new_test_multi_config(
+                &app(),
+                vec![Box::new(CommandLineVcl::new(
+                    ArgsBuilder::new().param("--neighbors", neighbors).into(),
+                ))],
+            )
+            .unwrap();
+            standard::convert_ci_configs(&multi_config)
+        };
+
+        let signed_result = parse(&signed);
+        let impostor_result = parse(&impostor);
+
+        assert_eq!(
+            signed_result,
+            Ok(Some(vec![NodeDescriptor::from_str(
+                &CryptDEReal::new(DEFAULT_CHAIN_ID),
+                descriptor
+            )
+            .unwrap()]))
+        );
+        assert_eq!(
+            impostor_result,
+            Err(ConfiguratorError::required(
+                "neighbors",
+                &format!(
+                    "Descriptor {} wasn't signed by earning wallet {}; it may be an impersonation",
+                    descriptor,
+                    make_wallet("impostor")
+                )
+            ))
+        );
+    }
+
+    #[test]
+    fn make_neighborhood_config_shows_and_remembers_the_wallet_that_signed_each_neighbor() {
+        running_test();
+        let signed_descriptor = "mhtjjdMt7Gyoebtb1yiK0hdaUx6j84noHdaAHeDR1S4@1.2.3.4:1234;2345";
+        let unsigned_descriptor = "Si06R3ulkOjJOLw1r2R9GOsY87yuinHU/IHK2FJyGnk@2.3.4.5:3456;4567";
+        let operator_wallet = make_paying_wallet(b"neighbor operator");
+        let signed = sign_descriptor(signed_descriptor, &operator_wallet).unwrap();
+        let multi_config = make_new_test_multi_config(
+            &app(),
+            vec![Box::new(CommandLineVcl::new(
+                ArgsBuilder::new()
+                    .param("--neighborhood-mode", "standard")
+                    .param("--ip", "1.2.3.4")
+                    .param(
+                        "--neighbors",
+                        &format!("{},{}", signed, unsigned_descriptor),
+                    )
+                    .into(),
+            ))],
+        )
+        .unwrap();
+        let mut holder = FakeStreamHolder::new();
+        let mut config = BootstrapperConfig::new();
+
+        let result = standard::make_neighborhood_config(
+            &multi_config,
+            &mut holder.streams(),
+            Some(&mut make_default_persistent_configuration()),
+            &mut config,
+        );
+
+        assert!(result.is_ok());
+        let dummy_cryptde = CryptDEReal::new(DEFAULT_CHAIN_ID);
+        let signed_key = NodeDescriptor::from_str(&dummy_cryptde, signed_descriptor)
+            .unwrap()
+            .encryption_public_key;
+        assert_eq!(
+            config.neighbor_signers,
+            vec![(signed_key.clone(), operator_wallet.as_address_wallet())]
+                .into_iter()
+                .collect::<std::collections::HashMap<PublicKey, Wallet>>()
+        );
+        assert_eq!(
+            holder.stdout.get_string(),
+            format!(
+                "Neighbor {} is vouched for by earning wallet {}; make sure that's the operator you expect\n",
+                signed_key, operator_wallet
+            )
+        );
+    }
+
     #[test]
     fn can_read_parameters_from_config_file() {
         running_test();
@@ -1919,6 +2930,7 @@ mod tests {
             .param("--ui-port", "5335")
             .param("--data-directory", home_dir.to_str().unwrap())
             .param("--blockchain-service-url", "http://127.0.0.1:8545")
+            .param("--price-feed-url", "https://prices.example.com/masq")
             .param("--log-level", "trace")
             .param("--fake-public-key", "AQIDBA")
             .param("--db-password", "secret-db-password")
@@ -1966,6 +2978,10 @@ mod tests {
             config.blockchain_bridge_config.blockchain_service_url,
             Some("http://127.0.0.1:8545".to_string()),
         );
+        assert_eq!(
+            config.blockchain_bridge_config.price_feed_url_opt,
+            Some("https://prices.example.com/masq".to_string()),
+        );
         assert_eq!(config.data_directory, home_dir);
         assert_eq!(
             config.main_cryptde_null_opt.unwrap().public_key(),
@@ -2003,6 +3019,7 @@ mod tests {
             )
             .param("--ip", "34.56.78.90")
             .param("--clandestine-port", "1234")
+            .param("--additional-clandestine-ports", "8443,9000-9001")
             .param("--ui-port", "5335")
             .param("--data-directory", home_dir.to_str().unwrap())
             .param("--blockchain-service-url", "http://127.0.0.1:8545")
@@ -2037,6 +3054,7 @@ mod tests {
             Wallet::from_str("0x0123456789012345678901234567890123456789").unwrap()
         );
         assert_eq!(Some(1234u16), config.clandestine_port_opt);
+        assert_eq!(config.additional_clandestine_ports, vec![8443, 9000, 9001]);
         assert_eq!(
             config.earning_wallet,
             Wallet::from_str("0x0123456789012345678901234567890123456789").unwrap()
@@ -2085,6 +3103,7 @@ mod tests {
             value_m!(multi_config, "config-file", PathBuf)
         );
         assert_eq!(None, config.clandestine_port_opt);
+        assert_eq!(config.additional_clandestine_ports, Vec::<u16>::new());
         assert!(config
             .neighborhood_config
             .mode
@@ -2101,6 +3120,7 @@ mod tests {
         );
         assert_eq!(config.earning_wallet, DEFAULT_EARNING_WALLET.clone(),);
         assert_eq!(config.consuming_wallet, None,);
+        assert_eq!(config.blockchain_bridge_config.payment_token_opt, None);
     }
 
     #[test]
@@ -2174,45 +3194,387 @@ mod tests {
     }
 
     #[test]
-    fn privileged_parse_args_creates_configuration_with_defaults() {
-        running_test();
-        let args = ArgsBuilder::new().param("--ip", "1.2.3.4");
-        let mut config = BootstrapperConfig::new();
-        let vcls: Vec<Box<dyn VirtualCommandLine>> =
-            vec![Box::new(CommandLineVcl::new(args.into()))];
-        let multi_config = make_new_test_multi_config(&app(), vcls).unwrap();
+    fn unprivileged_parse_args_takes_log_rotation_from_command_line_then_database_then_defaults() {
+        let multi_config = test_utils::make_multi_config(
+            ArgsBuilder::new()
+                .param("--ip", "1.2.3.4")
+                .param("--log-rotation-size", "0"),
+        );
+        let mut unprivileged_config = BootstrapperConfig::new();
+        let mut holder = FakeStreamHolder::new();
+        let mut persistent_config = PersistentConfigurationMock::new()
+            .gas_price_result(Ok(None))
+            .log_rotation_size_mb_result(Ok(Some(20)))
+            .log_rotation_age_hours_result(Ok(Some(24)))
+            .log_retention_result(Ok(None))
+            .earning_wallet_from_address_result(Ok(Some(Wallet::new(
+                "0x0123456789012345678901234567890123456789",
+            ))))
+            .mnemonic_seed_exists_result(Ok(false));
 
-        standard::privileged_parse_args(
-            &RealDirsWrapper {},
+        standard::unprivileged_parse_args(
             &multi_config,
-            &mut config,
-            &mut FakeStreamHolder::new().streams(),
+            &mut unprivileged_config,
+            &mut holder.streams(),
+            Some(&mut persistent_config),
         )
         .unwrap();
 
         assert_eq!(
-            Some(PathBuf::from("config.toml")),
-            value_m!(multi_config, "config-file", PathBuf)
-        );
-        assert_eq!(
-            config.dns_servers,
-            vec!(SocketAddr::from_str("1.1.1.1:53").unwrap())
-        );
-        assert_eq!(config.crash_point, CrashPoint::None);
-        assert_eq!(config.ui_gateway_config.ui_port, DEFAULT_UI_PORT);
-        assert!(config.main_cryptde_null_opt.is_none());
-        assert_eq!(
-            config.real_user,
-            RealUser::new(None, None, None).populate(&RealDirsWrapper {})
+            unprivileged_config.log_rotation,
+            LogRotationConfig {
+                size_mb: 0,
+                age_hours: 24,
+                retention: 50,
+            }
         );
     }
 
     #[test]
-    #[cfg(not(target_os = "windows"))]
-    fn privileged_parse_args_with_real_user_defaults_data_directory_properly() {
-        running_test();
-        let args = ArgsBuilder::new()
-            .param("--ip", "1.2.3.4")
+    fn unprivileged_parse_args_records_the_payment_token_in_a_new_database() {
+        let multi_config =
+            test_utils::make_multi_config(ArgsBuilder::new().param("--ip", "1.2.3.4").param(
+                "--payment-token",
+                "USDC:0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48:6",
+            ));
+        let mut unprivileged_config = BootstrapperConfig::new();
+        let mut holder = FakeStreamHolder::new();
+        let set_payment_token_params_arc = Arc::new(Mutex::new(vec![]));
+        let mut persistent_config = PersistentConfigurationMock::new()
+            .gas_price_result(Ok(None))
+            .payment_token_result(Ok(None))
+            .set_payment_token_params(&set_payment_token_params_arc)
+            .earning_wallet_from_address_result(Ok(Some(Wallet::new(
+                "0x0123456789012345678901234567890123456789",
+            ))))
+            .mnemonic_seed_exists_result(Ok(false));
+
+        standard::unprivileged_parse_args(
+            &multi_config,
+            &mut unprivileged_config,
+            &mut holder.streams(),
+            Some(&mut persistent_config),
+        )
+        .unwrap();
+
+        assert_eq!(
+            unprivileged_config
+                .blockchain_bridge_config
+                .payment_token_opt,
+            Some(
+                PaymentToken::from_str("USDC:0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48:6")
+                    .unwrap()
+            )
+        );
+        assert_eq!(
+            *set_payment_token_params_arc.lock().unwrap(),
+            vec!["USDC:0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48:6".to_string()]
+        );
+    }
+
+    #[test]
+    fn unprivileged_parse_args_refuses_to_settle_a_database_in_another_token() {
+        let multi_config =
+            test_utils::make_multi_config(ArgsBuilder::new().param("--ip", "1.2.3.4"));
+        let mut unprivileged_config = BootstrapperConfig::new();
+        let mut holder = FakeStreamHolder::new();
+        let stored = "USDC:0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48:6";
+        let mut persistent_config = PersistentConfigurationMock::new()
+            .gas_price_result(Ok(None))
+            .payment_token_result(Ok(Some(stored.to_string())));
+
+        let result = standard::unprivileged_parse_args(
+            &multi_config,
+            &mut unprivileged_config,
+            &mut holder.streams(),
+            Some(&mut persistent_config),
+        );
+
+        assert_eq!(
+            result,
+            Err(ConfiguratorError::required(
+                "payment-token",
+                &format!(
+                    "This database keeps its accounts in {}, so it can't settle in {}; give each \
+                     payment token its own --data-directory",
+                    stored,
+                    default_payment_token(unprivileged_config.blockchain_bridge_config.chain_id)
+                )
+            ))
+        );
+    }
+
+    #[test]
+    fn unprivileged_parse_args_complains_when_log_rotation_cant_be_read() {
+        let multi_config =
+            test_utils::make_multi_config(ArgsBuilder::new().param("--ip", "1.2.3.4"));
+        let mut unprivileged_config = BootstrapperConfig::new();
+        let mut holder = FakeStreamHolder::new();
+        let mut persistent_config = PersistentConfigurationMock::new()
+            .gas_price_result(Ok(None))
+            .log_rotation_size_mb_result(Err(PersistentConfigError::NotPresent));
+
+        let result = standard::unprivileged_parse_args(
+            &multi_config,
+            &mut unprivileged_config,
+            &mut holder.streams(),
+            Some(&mut persistent_config),
+        );
+
+        assert_eq!(
+            result,
+            Err(PersistentConfigError::NotPresent.into_configurator_error("log-rotation-size"))
+        );
+    }
+
+    #[test]
+    fn unprivileged_parse_args_reads_key_rotation_parameters() {
+        let multi_config = test_utils::make_multi_config(
+            ArgsBuilder::new()
+                .param("--ip", "1.2.3.4")
+                .param("--clandestine-key-lifetime", "30")
+                .param("--clandestine-key-overlap", "12"),
+        );
+        let mut unprivileged_config = BootstrapperConfig::new();
+        unprivileged_config.db_password_opt = Some("password".to_string());
+        let mut holder = FakeStreamHolder::new();
+        let mut persistent_config =
+            make_persistent_config(None, Some("password"), None, None, None, None, None);
+
+        standard::unprivileged_parse_args(
+            &multi_config,
+            &mut unprivileged_config,
+            &mut holder.streams(),
+            Some(&mut persistent_config),
+        )
+        .unwrap();
+
+        assert_eq!(
+            unprivileged_config.key_rotation_opt,
+            Some(KeyRotationConfig {
+                lifetime: Duration::from_secs(30 * 86400),
+                overlap: Duration::from_secs(12 * 3600),
+            })
+        );
+    }
+
+    #[test]
+    fn unprivileged_parse_args_reads_post_quantum() {
+        let multi_config = test_utils::make_multi_config(
+            ArgsBuilder::new()
+                .param("--ip", "1.2.3.4")
+                .param("--post-quantum", "On"),
+        );
+        let mut unprivileged_config = BootstrapperConfig::new();
+        unprivileged_config.db_password_opt = Some("password".to_string());
+        let mut holder = FakeStreamHolder::new();
+        let mut persistent_config =
+            make_persistent_config(None, Some("password"), None, None, None, None, None);
+
+        standard::unprivileged_parse_args(
+            &multi_config,
+            &mut unprivileged_config,
+            &mut holder.streams(),
+            Some(&mut persistent_config),
+        )
+        .unwrap();
+
+        assert_eq!(unprivileged_config.post_quantum, true);
+    }
+
+    #[test]
+    fn unprivileged_parse_args_makes_a_local_proxy_without_wallets_or_a_password() {
+        let multi_config = test_utils::make_multi_config(ArgsBuilder::new().opt("--local-proxy"));
+        let mut unprivileged_config = BootstrapperConfig::new();
+        let mut holder = FakeStreamHolder::new();
+        let mut persistent_config = make_persistent_config(
+            Some("mnemonic"),
+            None,
+            None,
+            Some("m/44'/60'/0'/0/0"),
+            None,
+            None,
+            None,
+        );
+
+        standard::unprivileged_parse_args(
+            &multi_config,
+            &mut unprivileged_config,
+            &mut holder.streams(),
+            Some(&mut persistent_config),
+        )
+        .unwrap();
+
+        assert_eq!(unprivileged_config.local_proxy, true);
+        assert_eq!(
+            unprivileged_config.neighborhood_config.mode,
+            NeighborhoodMode::ZeroHop
+        );
+        assert_eq!(unprivileged_config.consuming_wallet, None);
+        assert_eq!(
+            unprivileged_config.earning_wallet,
+            DEFAULT_EARNING_WALLET.clone()
+        );
+        assert_eq!(holder.stdout.get_string(), "");
+    }
+
+    #[test]
+    fn unprivileged_parse_args_refuses_a_local_proxy_that_would_be_decentralized() {
+        let parse = |args: ArgsBuilder| {
+            let multi_config = test_utils::make_multi_config(args);
+            let mut unprivileged_config = BootstrapperConfig::new();
+            let result = standard::unprivileged_parse_args(
+                &multi_config,
+                &mut unprivileged_config,
+                &mut FakeStreamHolder::new().streams(),
+                None,
+            );
+            result.map(|_| unprivileged_config.local_proxy)
+        };
+
+        let standard_mode = parse(
+            ArgsBuilder::new()
+                .param("--local-proxy", "on")
+                .param("--neighborhood-mode", "standard"),
+        );
+        let with_neighbors = parse(
+            ArgsBuilder::new()
+                .opt("--local-proxy")
+                .param("--neighbors", "anything"),
+        );
+        let with_ip = parse(
+            ArgsBuilder::new()
+                .opt("--local-proxy")
+                .param("--ip", "1.2.3.4"),
+        );
+        let turned_off = parse(
+            ArgsBuilder::new()
+                .param("--local-proxy", "off")
+                .param("--neighborhood-mode", "zero-hop"),
+        );
+
+        assert_eq!(
+            standard_mode,
+            Err(ConfiguratorError::conflict(
+                &["local-proxy", "neighborhood-mode"],
+                "Node cannot run as a --local-proxy in --neighborhood-mode standard"
+            ))
+        );
+        assert_eq!(
+            with_neighbors,
+            Err(ConfiguratorError::conflict(
+                &["local-proxy", "neighbors"],
+                "Node cannot run as a --local-proxy if --neighbors is specified"
+            ))
+        );
+        assert_eq!(
+            with_ip,
+            Err(ConfiguratorError::conflict(
+                &["local-proxy", "ip"],
+                "Node cannot run as a --local-proxy if --ip is specified"
+            ))
+        );
+        assert_eq!(turned_off, Ok(false));
+    }
+
+    #[test]
+    fn unprivileged_parse_args_requires_db_password_for_key_rotation() {
+        let multi_config = test_utils::make_multi_config(
+            ArgsBuilder::new()
+                .param("--ip", "1.2.3.4")
+                .param("--clandestine-key-lifetime", "30"),
+        );
+        let mut unprivileged_config = BootstrapperConfig::new();
+        let mut holder = FakeStreamHolder::new();
+        let mut persistent_config = PersistentConfigurationMock::new().gas_price_result(Ok(None));
+
+        let result = standard::unprivileged_parse_args(
+            &multi_config,
+            &mut unprivileged_config,
+            &mut holder.streams(),
+            Some(&mut persistent_config),
+        );
+
+        assert_eq!(
+            result,
+            Err(ConfiguratorError::new(vec![ParamError::with_code(
+                "db-password",
+                ParamErrorCode::Required,
+                "Must be specified with --clandestine-key-lifetime, so that the key can be kept in the database",
+            )]))
+        );
+    }
+
+    #[test]
+    fn privileged_parse_args_creates_configuration_with_defaults() {
+        running_test();
+        let args = ArgsBuilder::new().param("--ip", "1.2.3.4");
+        let mut config = BootstrapperConfig::new();
+        let vcls: Vec<Box<dyn VirtualCommandLine>> =
+            vec![Box::new(CommandLineVcl::new(args.into()))];
+        let multi_config = make_new_test_multi_config(&app(), vcls).unwrap();
+
+        standard::privileged_parse_args(
+            &RealDirsWrapper {},
+            &multi_config,
+            &mut config,
+            &mut FakeStreamHolder::new().streams(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            Some(PathBuf::from("config.toml")),
+            value_m!(multi_config, "config-file", PathBuf)
+        );
+        assert_eq!(
+            config.dns_servers,
+            vec!(SocketAddr::from_str("1.1.1.1:53").unwrap())
+        );
+        assert_eq!(config.crash_point, CrashPoint::None);
+        assert_eq!(config.ui_gateway_config.ui_port, DEFAULT_UI_PORT);
+        assert_eq!(config.ui_gateway_config.tls_opt, None);
+        assert_eq!(config.ui_gateway_config.auth_opt, None);
+        assert_eq!(config.socks_config_opt, None);
+        assert_eq!(config.secure_dns_servers, vec![]);
+        assert_eq!(config.dns_cache_size, DEFAULT_DNS_CACHE_SIZE);
+        assert_eq!(config.dns_lookup_threads, DEFAULT_DNS_LOOKUP_THREADS);
+        assert_eq!(config.route_search_threads, DEFAULT_ROUTE_SEARCH_THREADS);
+        assert_eq!(config.exit_policy, ExitPolicy::default());
+        assert_eq!(config.reputation_lists, Vec::<String>::new());
+        assert_eq!(config.masquerade_profiles, Vec::<String>::new());
+        assert_eq!(
+            config.clock_skew_tolerance_secs,
+            DEFAULT_CLOCK_SKEW_TOLERANCE_SECS
+        );
+        assert_eq!(
+            config.time_servers,
+            vec![
+                "pool.ntp.org".to_string(),
+                "time.cloudflare.com".to_string()
+            ]
+        );
+        assert_eq!(config.descriptor_signature_opt, None);
+        assert_eq!(config.stream_idle_timeout_opt, None);
+        assert_eq!(config.multipath_mode, MultipathMode::Off);
+        assert_eq!(config.encrypted_sni_fallback, EncryptedSniFallback::Refuse);
+        assert_eq!(config.dns_port, 53);
+        assert_eq!(config.proxied_domains_opt, None);
+        assert_eq!(config.metrics_port_opt, None);
+        assert_eq!(config.update_check_opt, None);
+        assert_eq!(config.geo_db_opt, None);
+        assert!(config.main_cryptde_null_opt.is_none());
+        assert_eq!(
+            config.real_user,
+            RealUser::new(None, None, None).populate(&RealDirsWrapper {})
+        );
+    }
+
+    #[test]
+    #[cfg(not(target_os = "windows"))]
+    fn privileged_parse_args_with_real_user_defaults_data_directory_properly() {
+        running_test();
+        let args = ArgsBuilder::new()
+            .param("--ip", "1.2.3.4")
             .param("--real-user", "::/home/booga");
         let mut config = BootstrapperConfig::new();
         let vcls: Vec<Box<dyn VirtualCommandLine>> =
@@ -2429,7 +3791,8 @@ mod tests {
             None,
             None,
         )
-        .check_password_result(Ok(false));
+        .check_password_result(Ok(false))
+        .check_password_result(Ok(true));
         let mut config = BootstrapperConfig::new();
 
         let result = standard::get_wallets(
@@ -2472,7 +3835,7 @@ mod tests {
         .err();
 
         assert_eq! (result, Some (ConfiguratorError::new (vec![
-            ParamError::new ("earning-wallet", "Cannot change to an address (0x0123456789012345678901234567890123456789) different from that previously set (0x9876543210987654321098765432109876543210)")
+            ParamError::new ("earning-wallet", "Cannot change to an address (0x0123456789012345678901234567890123456789) different from that previously set (0x9876543210987654321098765432109876543210) without --earning-wallet-change on")
         ])));
     }
 
@@ -2538,9 +3901,10 @@ mod tests {
         )
         .err();
 
-        assert_eq! (result, Some (ConfiguratorError::new (vec![
-            ParamError::new ("consuming-private-key", "Cannot use --consuming-private-key and --earning-wallet when database contains mnemonic seed")
-        ])));
+        assert_eq! (result, Some (ConfiguratorError::conflict (
+            &["consuming-private-key", "earning-wallet"],
+            "Cannot use --consuming-private-key and --earning-wallet when database contains mnemonic seed"
+        )));
     }
 
     #[test]
@@ -2638,7 +4002,8 @@ mod tests {
             None,
             None,
         )
-        .check_password_result(Ok(false));
+        .check_password_result(Ok(false))
+        .check_password_result(Ok(true));
         let mut config = BootstrapperConfig::new();
 
         standard::get_wallets(
@@ -2708,7 +4073,8 @@ mod tests {
         )
         .check_password_result(Ok(false))
         .check_password_result(Ok(true))
-        .check_password_result(Ok(false));
+        .check_password_result(Ok(false))
+        .check_password_result(Ok(true));
         let mut config = BootstrapperConfig::new();
         let mut stdout_writer = ByteArrayWriter::new();
         let mut streams = &mut StdStreams {
@@ -2951,22 +4317,957 @@ mod tests {
     }
 
     #[test]
-    fn privileged_generate_configuration_senses_when_user_specifies_config_file() {
+    fn privileged_parse_args_creates_socks_configuration_and_listener() {
         running_test();
-        let subject = NodeConfiguratorStandardPrivileged::new();
-        let args = ArgsBuilder::new().param("--config-file", "booga.toml"); // nonexistent config file: should stimulate panic because user-specified
-        let args_vec: Vec<String> = args.into();
+        let args = make_default_cli_params()
+            .param("--socks-port", "1080")
+            .param("--socks-credentials", "booga:agoob:wibble");
+        let mut config = BootstrapperConfig::new();
+        let vcl = Box::new(CommandLineVcl::new(args.into()));
+        let multi_config = make_new_test_multi_config(&app(), vec![vcl]).unwrap();
 
-        let result = subject
-            .configure(args_vec.as_slice(), &mut FakeStreamHolder::new().streams())
-            .err();
+        standard::privileged_parse_args(
+            &RealDirsWrapper {},
+            &multi_config,
+            &mut config,
+            &mut FakeStreamHolder::new().streams(),
+        )
+        .unwrap();
 
-        match result {
-            None => panic!("Expected a value, got None"),
-            Some(mut error) => {
-                assert_eq!(error.param_errors.len(), 1);
-                let param_error = error.param_errors.remove(0);
-                assert_eq!(param_error.parameter, "config-file".to_string());
+        assert_eq!(
+            config.socks_config_opt,
+            Some(SocksConfig {
+                port: 1080,
+                credentials_opt: Some(SocksCredentials {
+                    username: "booga".to_string(),
+                    password: "agoob:wibble".to_string(),
+                }),
+            })
+        );
+        let port_configuration = config.port_configurations.get(&1080).unwrap();
+        assert_eq!(port_configuration.is_clandestine, false);
+        assert_eq!(port_configuration.discriminator_factories.len(), 1);
+    }
+
+    #[test]
+    fn privileged_parse_args_creates_socks_configuration_without_credentials() {
+        running_test();
+        let args = make_default_cli_params().param("--socks-port", "1080");
+        let mut config = BootstrapperConfig::new();
+        let vcl = Box::new(CommandLineVcl::new(args.into()));
+        let multi_config = make_new_test_multi_config(&app(), vec![vcl]).unwrap();
+
+        standard::privileged_parse_args(
+            &RealDirsWrapper {},
+            &multi_config,
+            &mut config,
+            &mut FakeStreamHolder::new().streams(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.socks_config_opt,
+            Some(SocksConfig {
+                port: 1080,
+                credentials_opt: None,
+            })
+        );
+    }
+
+    #[test]
+    fn privileged_parse_args_reads_secure_dns_servers() {
+        running_test();
+        let args = make_default_cli_params().param(
+            "--secure-dns-servers",
+            "https://cloudflare-dns.com/dns-query,tls://9.9.9.9",
+        );
+        let mut config = BootstrapperConfig::new();
+        let vcl = Box::new(CommandLineVcl::new(args.into()));
+        let multi_config = make_new_test_multi_config(&app(), vec![vcl]).unwrap();
+
+        standard::privileged_parse_args(
+            &RealDirsWrapper {},
+            &multi_config,
+            &mut config,
+            &mut FakeStreamHolder::new().streams(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.secure_dns_servers,
+            vec![
+                SecureDnsServer::from_str("https://cloudflare-dns.com:443/dns-query").unwrap(),
+                SecureDnsServer::from_str("tls://9.9.9.9:853").unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn privileged_parse_args_reads_exit_policy() {
+        running_test();
+        let args = make_default_cli_params().param(
+            "--exit-policy",
+            "port:6881-6889,host:example.com,category:mail",
+        );
+        let mut config = BootstrapperConfig::new();
+        let vcl = Box::new(CommandLineVcl::new(args.into()));
+        let multi_config = make_new_test_multi_config(&app(), vec![vcl]).unwrap();
+
+        standard::privileged_parse_args(
+            &RealDirsWrapper {},
+            &multi_config,
+            &mut config,
+            &mut FakeStreamHolder::new().streams(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.exit_policy,
+            ExitPolicy {
+                denied_ports: vec![(6881, 6889)],
+                denied_hosts: vec!["example.com".to_string()],
+                denied_categories: vec![ContentCategory::Mail],
+            }
+        );
+    }
+
+    #[test]
+    fn privileged_parse_args_reads_log_redaction() {
+        running_test();
+        let parse = |args: ArgsBuilder| {
+            let mut config = BootstrapperConfig::new();
+            let vcl = Box::new(CommandLineVcl::new(args.into()));
+            let multi_config = make_new_test_multi_config(&app(), vec![vcl]).unwrap();
+            standard::privileged_parse_args(
+                &RealDirsWrapper {},
+                &multi_config,
+                &mut config,
+                &mut FakeStreamHolder::new().streams(),
+            )
+            .unwrap();
+            config.log_redaction
+        };
+
+        let default_result = parse(make_default_cli_params());
+        let off_result = parse(make_default_cli_params().param("--log-redaction", "Off"));
+        let on_result = parse(make_default_cli_params().param("--log-redaction", "on"));
+
+        assert_eq!(default_result, true);
+        assert_eq!(off_result, false);
+        assert_eq!(on_result, true);
+    }
+
+    #[test]
+    fn privileged_parse_args_reads_port_reselection() {
+        running_test();
+        let parse = |args: ArgsBuilder| {
+            let mut config = BootstrapperConfig::new();
+            let vcl = Box::new(CommandLineVcl::new(args.into()));
+            let multi_config = make_new_test_multi_config(&app(), vec![vcl]).unwrap();
+            standard::privileged_parse_args(
+                &RealDirsWrapper {},
+                &multi_config,
+                &mut config,
+                &mut FakeStreamHolder::new().streams(),
+            )
+            .unwrap();
+            config.port_reselection
+        };
+
+        let default_result = parse(make_default_cli_params());
+        let on_result = parse(make_default_cli_params().param("--port-reselection", "On"));
+        let off_result = parse(make_default_cli_params().param("--port-reselection", "off"));
+
+        assert_eq!(default_result, false);
+        assert_eq!(on_result, true);
+        assert_eq!(off_result, false);
+    }
+
+    #[test]
+    fn privileged_parse_args_reads_sandbox() {
+        running_test();
+        let parse = |args: ArgsBuilder| {
+            let mut config = BootstrapperConfig::new();
+            let vcl = Box::new(CommandLineVcl::new(args.into()));
+            let multi_config = make_new_test_multi_config(&app(), vec![vcl]).unwrap();
+            standard::privileged_parse_args(
+                &RealDirsWrapper {},
+                &multi_config,
+                &mut config,
+                &mut FakeStreamHolder::new().streams(),
+            )
+            .unwrap();
+            config.sandbox
+        };
+
+        let default_result = parse(make_default_cli_params());
+        let on_result = parse(make_default_cli_params().param("--sandbox", "on"));
+        let off_result = parse(make_default_cli_params().param("--sandbox", "Off"));
+
+        assert_eq!(default_result, true);
+        assert_eq!(on_result, true);
+        assert_eq!(off_result, false);
+    }
+
+    #[test]
+    fn privileged_parse_args_reads_local_discovery() {
+        running_test();
+        let parse = |args: ArgsBuilder| {
+            let mut config = BootstrapperConfig::new();
+            let vcl = Box::new(CommandLineVcl::new(args.into()));
+            let multi_config = make_new_test_multi_config(&app(), vec![vcl]).unwrap();
+            standard::privileged_parse_args(
+                &RealDirsWrapper {},
+                &multi_config,
+                &mut config,
+                &mut FakeStreamHolder::new().streams(),
+            )
+            .unwrap();
+            config.local_discovery
+        };
+
+        let default_result = parse(make_default_cli_params());
+        let on_result = parse(make_default_cli_params().param("--local-discovery", "ON"));
+        let off_result = parse(make_default_cli_params().param("--local-discovery", "off"));
+
+        assert_eq!(default_result, false);
+        assert_eq!(on_result, true);
+        assert_eq!(off_result, false);
+    }
+
+    #[test]
+    fn privileged_parse_args_reads_bandwidth_benchmark() {
+        running_test();
+        let parse = |args: ArgsBuilder| {
+            let mut config = BootstrapperConfig::new();
+            let vcl = Box::new(CommandLineVcl::new(args.into()));
+            let multi_config = make_new_test_multi_config(&app(), vec![vcl]).unwrap();
+            standard::privileged_parse_args(
+                &RealDirsWrapper {},
+                &multi_config,
+                &mut config,
+                &mut FakeStreamHolder::new().streams(),
+            )
+            .unwrap();
+            config.bandwidth_benchmark
+        };
+
+        let default_result = parse(make_default_cli_params());
+        let on_result = parse(make_default_cli_params().param("--bandwidth-benchmark", "ON"));
+        let off_result = parse(make_default_cli_params().param("--bandwidth-benchmark", "off"));
+
+        assert_eq!(default_result, false);
+        assert_eq!(on_result, true);
+        assert_eq!(off_result, false);
+    }
+
+    #[test]
+    fn privileged_parse_args_reads_hop_timing() {
+        running_test();
+        let parse = |args: ArgsBuilder| {
+            let mut config = BootstrapperConfig::new();
+            let vcl = Box::new(CommandLineVcl::new(args.into()));
+            let multi_config = make_new_test_multi_config(&app(), vec![vcl]).unwrap();
+            standard::privileged_parse_args(
+                &RealDirsWrapper {},
+                &multi_config,
+                &mut config,
+                &mut FakeStreamHolder::new().streams(),
+            )
+            .unwrap();
+            config.hop_timing
+        };
+
+        let default_result = parse(make_default_cli_params());
+        let on_result = parse(make_default_cli_params().param("--hop-timing", "On"));
+        let off_result = parse(make_default_cli_params().param("--hop-timing", "OFF"));
+
+        assert_eq!(default_result, false);
+        assert_eq!(on_result, true);
+        assert_eq!(off_result, false);
+    }
+
+    #[test]
+    fn privileged_parse_args_reads_masquerade_profiles() {
+        running_test();
+        let args = make_default_cli_params().param("--masquerade-profiles", "websocket,json");
+        let mut config = BootstrapperConfig::new();
+        let vcl = Box::new(CommandLineVcl::new(args.into()));
+        let multi_config = make_new_test_multi_config(&app(), vec![vcl]).unwrap();
+
+        standard::privileged_parse_args(
+            &RealDirsWrapper {},
+            &multi_config,
+            &mut config,
+            &mut FakeStreamHolder::new().streams(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.masquerade_profiles,
+            vec!["websocket".to_string(), "json".to_string()]
+        );
+    }
+
+    #[test]
+    fn privileged_parse_args_reads_clock_skew_tolerance_and_time_servers() {
+        running_test();
+        let parse = |args: ArgsBuilder| {
+            let mut config = BootstrapperConfig::new();
+            let vcl = Box::new(CommandLineVcl::new(args.into()));
+            let multi_config = make_new_test_multi_config(&app(), vec![vcl]).unwrap();
+            standard::privileged_parse_args(
+                &RealDirsWrapper {},
+                &multi_config,
+                &mut config,
+                &mut FakeStreamHolder::new().streams(),
+            )
+            .unwrap();
+            (config.clock_skew_tolerance_secs, config.time_servers)
+        };
+
+        let chosen = parse(
+            make_default_cli_params()
+                .param("--clock-skew-tolerance", "30")
+                .param("--time-servers", "ntp.example.com,192.168.0.1:1123"),
+        );
+        let off = parse(make_default_cli_params().param("--time-servers", "off"));
+
+        assert_eq!(
+            chosen,
+            (
+                30,
+                vec![
+                    "ntp.example.com".to_string(),
+                    "192.168.0.1:1123".to_string()
+                ]
+            )
+        );
+        assert_eq!(off.1, Vec::<String>::new());
+    }
+
+    #[test]
+    fn privileged_parse_args_reads_descriptor_signature() {
+        running_test();
+        let signature = format!("0x{}1b", "ab".repeat(64));
+        let args = make_default_cli_params().param("--descriptor-signature", &signature);
+        let mut config = BootstrapperConfig::new();
+        let vcl = Box::new(CommandLineVcl::new(args.into()));
+        let multi_config = make_new_test_multi_config(&app(), vec![vcl]).unwrap();
+
+        standard::privileged_parse_args(
+            &RealDirsWrapper {},
+            &multi_config,
+            &mut config,
+            &mut FakeStreamHolder::new().streams(),
+        )
+        .unwrap();
+
+        assert_eq!(config.descriptor_signature_opt, Some(signature));
+    }
+
+    #[test]
+    fn privileged_parse_args_reads_gossip_recording() {
+        running_test();
+        let parse = |args: ArgsBuilder| {
+            let mut config = BootstrapperConfig::new();
+            let vcl = Box::new(CommandLineVcl::new(args.into()));
+            let multi_config = make_new_test_multi_config(&app(), vec![vcl]).unwrap();
+            standard::privileged_parse_args(
+                &RealDirsWrapper {},
+                &multi_config,
+                &mut config,
+                &mut FakeStreamHolder::new().streams(),
+            )
+            .unwrap();
+            config.gossip_recording
+        };
+
+        let default_result = parse(make_default_cli_params());
+        let on_result = parse(make_default_cli_params().param("--gossip-recording", "ON"));
+        let off_result = parse(make_default_cli_params().param("--gossip-recording", "off"));
+
+        assert_eq!(default_result, false);
+        assert_eq!(on_result, true);
+        assert_eq!(off_result, false);
+    }
+
+    #[test]
+    fn privileged_parse_args_reads_dump_neighborhood() {
+        running_test();
+        let parse = |args: ArgsBuilder| {
+            let mut config = BootstrapperConfig::new();
+            let vcl = Box::new(CommandLineVcl::new(args.into()));
+            let multi_config = make_new_test_multi_config(&app(), vec![vcl]).unwrap();
+            standard::privileged_parse_args(
+                &RealDirsWrapper {},
+                &multi_config,
+                &mut config,
+                &mut FakeStreamHolder::new().streams(),
+            )
+            .unwrap();
+            config.dump_neighborhood
+        };
+
+        let default_result = parse(make_default_cli_params());
+        let on_result = parse(make_default_cli_params().param("--dump-neighborhood", "On"));
+        let off_result = parse(make_default_cli_params().param("--dump-neighborhood", "OFF"));
+
+        assert_eq!(default_result, false);
+        assert_eq!(on_result, true);
+        assert_eq!(off_result, false);
+    }
+
+    #[test]
+    fn privileged_parse_args_reads_protocol_sniffing() {
+        running_test();
+        let parse = |args: ArgsBuilder| {
+            let mut config = BootstrapperConfig::new();
+            let vcl = Box::new(CommandLineVcl::new(args.into()));
+            let multi_config = make_new_test_multi_config(&app(), vec![vcl]).unwrap();
+            standard::privileged_parse_args(
+                &RealDirsWrapper {},
+                &multi_config,
+                &mut config,
+                &mut FakeStreamHolder::new().streams(),
+            )
+            .unwrap();
+            config.protocol_sniffing
+        };
+
+        let default_result = parse(make_default_cli_params());
+        let on_result = parse(make_default_cli_params().param("--protocol-sniffing", "on"));
+        let off_result = parse(make_default_cli_params().param("--protocol-sniffing", "Off"));
+
+        assert_eq!(default_result, false);
+        assert_eq!(on_result, true);
+        assert_eq!(off_result, false);
+    }
+
+    #[test]
+    fn privileged_parse_args_reads_attestation_settings() {
+        running_test();
+        let parse = |args: ArgsBuilder| {
+            let mut config = BootstrapperConfig::new();
+            let vcl = Box::new(CommandLineVcl::new(args.into()));
+            let multi_config = make_new_test_multi_config(&app(), vec![vcl]).unwrap();
+            standard::privileged_parse_args(
+                &RealDirsWrapper {},
+                &multi_config,
+                &mut config,
+                &mut FakeStreamHolder::new().streams(),
+            )
+            .unwrap();
+            (config.publish_attestations, config.heed_attestations)
+        };
+
+        let default_result = parse(make_default_cli_params());
+        let publish_result = parse(make_default_cli_params().param("--publish-attestations", "on"));
+        let heed_result = parse(
+            make_default_cli_params()
+                .param("--publish-attestations", "off")
+                .param("--heed-attestations", "ON"),
+        );
+
+        assert_eq!(default_result, (false, false));
+        assert_eq!(publish_result, (true, false));
+        assert_eq!(heed_result, (false, true));
+    }
+
+    #[test]
+    fn privileged_parse_args_reads_reputation_lists() {
+        running_test();
+        let args = make_default_cli_params()
+            .param("--reputation-lists", "Zen.Example.org.,bl.example.net");
+        let mut config = BootstrapperConfig::new();
+        let vcl = Box::new(CommandLineVcl::new(args.into()));
+        let multi_config = make_new_test_multi_config(&app(), vec![vcl]).unwrap();
+
+        standard::privileged_parse_args(
+            &RealDirsWrapper {},
+            &multi_config,
+            &mut config,
+            &mut FakeStreamHolder::new().streams(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.reputation_lists,
+            vec!["zen.example.org".to_string(), "bl.example.net".to_string()]
+        );
+    }
+
+    #[test]
+    fn privileged_parse_args_reads_deterministic_seed() {
+        running_test();
+        let parse = |args: ArgsBuilder| {
+            let mut config = BootstrapperConfig::new();
+            let vcl = Box::new(CommandLineVcl::new(args.into()));
+            let multi_config = make_new_test_multi_config(&app(), vec![vcl]).unwrap();
+            standard::privileged_parse_args(
+                &RealDirsWrapper {},
+                &multi_config,
+                &mut config,
+                &mut FakeStreamHolder::new().streams(),
+            )
+            .unwrap();
+            config.deterministic_seed_opt
+        };
+
+        let default_result = parse(make_default_cli_params());
+        let seeded_result = parse(make_default_cli_params().param("--deterministic-seed", "1234"));
+
+        assert_eq!(default_result, None);
+        assert_eq!(seeded_result, Some(1234));
+    }
+
+    #[test]
+    fn privileged_parse_args_reads_cpu_affinity() {
+        running_test();
+        let parse = |args: ArgsBuilder| {
+            let mut config = BootstrapperConfig::new();
+            let vcl = Box::new(CommandLineVcl::new(args.into()));
+            let multi_config = make_new_test_multi_config(&app(), vec![vcl]).unwrap();
+            standard::privileged_parse_args(
+                &RealDirsWrapper {},
+                &multi_config,
+                &mut config,
+                &mut FakeStreamHolder::new().streams(),
+            )
+            .unwrap();
+            config.cpu_affinity
+        };
+
+        let default_result = parse(make_default_cli_params());
+        let pinned_result = parse(make_default_cli_params().param("--cpu-affinity", "0,2-3"));
+
+        assert_eq!(default_result, Vec::<usize>::new());
+        assert_eq!(pinned_result, vec![0, 2, 3]);
+    }
+
+    #[test]
+    fn privileged_parse_args_reads_data_cap() {
+        running_test();
+        let parse = |args: ArgsBuilder| {
+            let mut config = BootstrapperConfig::new();
+            let vcl = Box::new(CommandLineVcl::new(args.into()));
+            let multi_config = make_new_test_multi_config(&app(), vec![vcl]).unwrap();
+            standard::privileged_parse_args(
+                &RealDirsWrapper {},
+                &multi_config,
+                &mut config,
+                &mut FakeStreamHolder::new().streams(),
+            )
+            .unwrap();
+            config.data_cap_opt
+        };
+
+        let default_result = parse(make_default_cli_params());
+        let capped_result = parse(
+            make_default_cli_params()
+                .param("--data-cap", "50")
+                .param("--data-cap-rates", "0-6:0,18-23:200"),
+        );
+
+        assert_eq!(default_result, None);
+        assert_eq!(
+            capped_result,
+            Some(DataCapConfig {
+                monthly_bytes: 50_000_000_000,
+                rates: vec![
+                    DataCapRate::from_str("0-6:0").unwrap(),
+                    DataCapRate::from_str("18-23:200").unwrap(),
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn privileged_parse_args_reads_stream_idle_timeout() {
+        running_test();
+        let args = make_default_cli_params().param("--stream-idle-timeout", "300");
+        let mut config = BootstrapperConfig::new();
+        let vcl = Box::new(CommandLineVcl::new(args.into()));
+        let multi_config = make_new_test_multi_config(&app(), vec![vcl]).unwrap();
+
+        standard::privileged_parse_args(
+            &RealDirsWrapper {},
+            &multi_config,
+            &mut config,
+            &mut FakeStreamHolder::new().streams(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.stream_idle_timeout_opt,
+            Some(Duration::from_secs(300))
+        );
+    }
+
+    #[test]
+    fn privileged_parse_args_treats_zero_stream_idle_timeout_as_none() {
+        running_test();
+        let args = make_default_cli_params().param("--stream-idle-timeout", "0");
+        let mut config = BootstrapperConfig::new();
+        let vcl = Box::new(CommandLineVcl::new(args.into()));
+        let multi_config = make_new_test_multi_config(&app(), vec![vcl]).unwrap();
+
+        standard::privileged_parse_args(
+            &RealDirsWrapper {},
+            &multi_config,
+            &mut config,
+            &mut FakeStreamHolder::new().streams(),
+        )
+        .unwrap();
+
+        assert_eq!(config.stream_idle_timeout_opt, None);
+    }
+
+    #[test]
+    fn privileged_parse_args_reads_multipath_mode() {
+        running_test();
+        let args = make_default_cli_params().param("--multipath", "Duplicate");
+        let mut config = BootstrapperConfig::new();
+        let vcl = Box::new(CommandLineVcl::new(args.into()));
+        let multi_config = make_new_test_multi_config(&app(), vec![vcl]).unwrap();
+
+        standard::privileged_parse_args(
+            &RealDirsWrapper {},
+            &multi_config,
+            &mut config,
+            &mut FakeStreamHolder::new().streams(),
+        )
+        .unwrap();
+
+        assert_eq!(config.multipath_mode, MultipathMode::Duplicate);
+    }
+
+    #[test]
+    fn privileged_parse_args_reads_encrypted_sni_fallback() {
+        running_test();
+        let args = make_default_cli_params().param("--encrypted-sni", "crypto.cloudflare.com");
+        let mut config = BootstrapperConfig::new();
+        let vcl = Box::new(CommandLineVcl::new(args.into()));
+        let multi_config = make_new_test_multi_config(&app(), vec![vcl]).unwrap();
+
+        standard::privileged_parse_args(
+            &RealDirsWrapper {},
+            &multi_config,
+            &mut config,
+            &mut FakeStreamHolder::new().streams(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.encrypted_sni_fallback,
+            EncryptedSniFallback::Host("crypto.cloudflare.com".to_string())
+        );
+    }
+
+    #[test]
+    fn privileged_parse_args_reads_dns_port_and_proxied_domains() {
+        running_test();
+        let args = make_default_cli_params()
+            .param("--dns-port", "5353")
+            .param("--proxied-domains", "Example.COM,example.org.");
+        let mut config = BootstrapperConfig::new();
+        let vcl = Box::new(CommandLineVcl::new(args.into()));
+        let multi_config = make_new_test_multi_config(&app(), vec![vcl]).unwrap();
+
+        standard::privileged_parse_args(
+            &RealDirsWrapper {},
+            &multi_config,
+            &mut config,
+            &mut FakeStreamHolder::new().streams(),
+        )
+        .unwrap();
+
+        assert_eq!(config.dns_port, 5353);
+        assert_eq!(
+            config.proxied_domains_opt,
+            Some(vec!["example.com".to_string(), "example.org".to_string()])
+        );
+    }
+
+    #[test]
+    fn privileged_parse_args_reads_dns_cache_size() {
+        running_test();
+        let parse = |args: ArgsBuilder| {
+            let mut config = BootstrapperConfig::new();
+            let vcl = Box::new(CommandLineVcl::new(args.into()));
+            let multi_config = make_new_test_multi_config(&app(), vec![vcl]).unwrap();
+            standard::privileged_parse_args(
+                &RealDirsWrapper {},
+                &multi_config,
+                &mut config,
+                &mut FakeStreamHolder::new().streams(),
+            )
+            .unwrap();
+            config.dns_cache_size
+        };
+
+        let chosen = parse(make_default_cli_params().param("--dns-cache-size", "5000"));
+        let off = parse(make_default_cli_params().param("--dns-cache-size", "0"));
+
+        assert_eq!(chosen, 5000);
+        assert_eq!(off, 0);
+    }
+
+    #[test]
+    fn privileged_parse_args_reads_thread_counts() {
+        running_test();
+        let args = make_default_cli_params()
+            .param("--dns-lookup-threads", "9")
+            .param("--route-search-threads", "2");
+        let mut config = BootstrapperConfig::new();
+        let vcl = Box::new(CommandLineVcl::new(args.into()));
+        let multi_config = make_new_test_multi_config(&app(), vec![vcl]).unwrap();
+
+        standard::privileged_parse_args(
+            &RealDirsWrapper {},
+            &multi_config,
+            &mut config,
+            &mut FakeStreamHolder::new().streams(),
+        )
+        .unwrap();
+
+        assert_eq!(config.dns_lookup_threads, 9);
+        assert_eq!(config.route_search_threads, 2);
+    }
+
+    #[test]
+    fn privileged_parse_args_reads_metrics_port() {
+        running_test();
+        let args = make_default_cli_params().param("--metrics-port", "9595");
+        let mut config = BootstrapperConfig::new();
+        let vcl = Box::new(CommandLineVcl::new(args.into()));
+        let multi_config = make_new_test_multi_config(&app(), vec![vcl]).unwrap();
+
+        standard::privileged_parse_args(
+            &RealDirsWrapper {},
+            &multi_config,
+            &mut config,
+            &mut FakeStreamHolder::new().streams(),
+        )
+        .unwrap();
+
+        assert_eq!(config.metrics_port_opt, Some(9595));
+    }
+
+    #[test]
+    fn privileged_parse_args_reads_update_check_parameters() {
+        running_test();
+        let key = "0123456789ABCDEF0123456789ABCDEF0123456789ABCDEF0123456789ABCDEF";
+        let args = make_default_cli_params()
+            .param(
+                "--update-check-url",
+                "https://releases.example.com/manifest.json",
+            )
+            .param("--update-check-key", key)
+            .param("--update-check-interval", "6");
+        let mut config = BootstrapperConfig::new();
+        let vcl = Box::new(CommandLineVcl::new(args.into()));
+        let multi_config = make_new_test_multi_config(&app(), vec![vcl]).unwrap();
+
+        standard::privileged_parse_args(
+            &RealDirsWrapper {},
+            &multi_config,
+            &mut config,
+            &mut FakeStreamHolder::new().streams(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.update_check_opt,
+            Some(UpdateCheckConfig {
+                manifest_url: "https://releases.example.com/manifest.json".to_string(),
+                public_key: key.from_hex().unwrap(),
+                interval: Duration::from_secs(6 * 3600),
+            })
+        );
+    }
+
+    #[test]
+    fn privileged_parse_args_reads_geo_db_parameters() {
+        running_test();
+        let key = "0123456789ABCDEF0123456789ABCDEF0123456789ABCDEF0123456789ABCDEF";
+        let args = make_default_cli_params()
+            .param("--geo-db-url", "https://geo.example.com/geo-ip.mmdb")
+            .param("--geo-db-key", key);
+        let mut config = BootstrapperConfig::new();
+        let vcl = Box::new(CommandLineVcl::new(args.into()));
+        let multi_config = make_new_test_multi_config(&app(), vec![vcl]).unwrap();
+
+        standard::privileged_parse_args(
+            &RealDirsWrapper {},
+            &multi_config,
+            &mut config,
+            &mut FakeStreamHolder::new().streams(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.geo_db_opt,
+            Some(GeoDbConfig {
+                mirror_url: "https://geo.example.com/geo-ip.mmdb".to_string(),
+                public_key: key.from_hex().unwrap(),
+                interval: Duration::from_secs(168 * 3600),
+            })
+        );
+    }
+
+    #[test]
+    fn privileged_parse_args_reads_ui_rest_port() {
+        running_test();
+        let args = make_default_cli_params().param("--ui-rest-port", "5334");
+        let mut config = BootstrapperConfig::new();
+        let vcl = Box::new(CommandLineVcl::new(args.into()));
+        let multi_config = make_new_test_multi_config(&app(), vec![vcl]).unwrap();
+
+        standard::privileged_parse_args(
+            &RealDirsWrapper {},
+            &multi_config,
+            &mut config,
+            &mut FakeStreamHolder::new().streams(),
+        )
+        .unwrap();
+
+        assert_eq!(config.ui_gateway_config.rest_port_opt, Some(5334));
+    }
+
+    #[test]
+    fn privileged_parse_args_reads_ui_grpc_port() {
+        running_test();
+        let args = make_default_cli_params().param("--ui-grpc-port", "5336");
+        let mut config = BootstrapperConfig::new();
+        let vcl = Box::new(CommandLineVcl::new(args.into()));
+        let multi_config = make_new_test_multi_config(&app(), vec![vcl]).unwrap();
+
+        standard::privileged_parse_args(
+            &RealDirsWrapper {},
+            &multi_config,
+            &mut config,
+            &mut FakeStreamHolder::new().streams(),
+        )
+        .unwrap();
+
+        assert_eq!(config.ui_gateway_config.grpc_port_opt, Some(5336));
+    }
+
+    #[test]
+    fn privileged_parse_args_reads_health_port_and_hands_over_the_data_directory() {
+        running_test();
+        let home_dir = ensure_node_home_directory_exists(
+            "node_configurator",
+            "privileged_parse_args_reads_health_port_and_hands_over_the_data_directory",
+        );
+        let args = make_default_cli_params()
+            .param("--health-port", "5335")
+            .param("--data-directory", home_dir.to_str().unwrap());
+        let mut config = BootstrapperConfig::new();
+        let vcl = Box::new(CommandLineVcl::new(args.into()));
+        let multi_config = make_new_test_multi_config(&app(), vec![vcl]).unwrap();
+
+        standard::privileged_parse_args(
+            &RealDirsWrapper {},
+            &multi_config,
+            &mut config,
+            &mut FakeStreamHolder::new().streams(),
+        )
+        .unwrap();
+
+        assert_eq!(config.ui_gateway_config.health_port_opt, Some(5335));
+        assert_eq!(config.ui_gateway_config.data_directory, home_dir);
+    }
+
+    #[test]
+    fn privileged_parse_args_keeps_a_generated_ui_certificate_in_the_data_directory() {
+        running_test();
+        let args = make_default_cli_params()
+            .param("--data-directory", "/home/booga/masq")
+            .param("--ui-tls", "on");
+        let mut config = BootstrapperConfig::new();
+        let vcl = Box::new(CommandLineVcl::new(args.into()));
+        let multi_config = make_new_test_multi_config(&app(), vec![vcl]).unwrap();
+
+        standard::privileged_parse_args(
+            &RealDirsWrapper {},
+            &multi_config,
+            &mut config,
+            &mut FakeStreamHolder::new().streams(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.ui_gateway_config.tls_opt,
+            Some(UiTlsConfig {
+                certificate_path: PathBuf::from("/home/booga/masq/ui-tls-certificate.pem"),
+                key_path: PathBuf::from("/home/booga/masq/ui-tls-key.pem"),
+                generate: true,
+                interfaces: vec![],
+                allowlist: vec![],
+                client_ca_path_opt: None,
+            })
+        );
+    }
+
+    #[test]
+    fn privileged_parse_args_reads_ui_passwords_and_ignores_empty_ones() {
+        running_test();
+        let args = make_default_cli_params()
+            .param("--ui-password", "booga")
+            .param("--ui-read-only-password", "");
+        let mut config = BootstrapperConfig::new();
+        let vcl = Box::new(CommandLineVcl::new(args.into()));
+        let multi_config = make_new_test_multi_config(&app(), vec![vcl]).unwrap();
+
+        standard::privileged_parse_args(
+            &RealDirsWrapper {},
+            &multi_config,
+            &mut config,
+            &mut FakeStreamHolder::new().streams(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.ui_gateway_config.auth_opt,
+            Some(UiAuthConfig {
+                full_password: "booga".to_string(),
+                read_only_password_opt: None,
+            })
+        );
+    }
+
+    #[test]
+    fn privileged_parse_args_insists_on_both_halves_of_a_supplied_ui_certificate() {
+        running_test();
+        let args = make_default_cli_params()
+            .param("--ui-tls", "on")
+            .param("--ui-tls-certificate", "/etc/masq/cert.pem");
+        let vcl = Box::new(CommandLineVcl::new(args.into()));
+
+        let result = make_new_test_multi_config(&app(), vec![vcl]);
+
+        assert_eq!(result.is_err(), true);
+    }
+
+    #[test]
+    fn privileged_generate_configuration_senses_when_user_specifies_config_file() {
+        running_test();
+        let subject = NodeConfiguratorStandardPrivileged::new();
+        let args = ArgsBuilder::new().param("--config-file", "booga.toml"); // nonexistent config file: should stimulate panic because user-specified
+        let args_vec: Vec<String> = args.into();
+
+        let result = subject
+            .configure(args_vec.as_slice(), &mut FakeStreamHolder::new().streams())
+            .err();
+
+        match result {
+            None => panic!("Expected a value, got None"),
+            Some(mut error) => {
+                assert_eq!(error.param_errors.len(), 1);
+                let param_error = error.param_errors.remove(0);
+                assert_eq!(param_error.parameter, "config-file".to_string());
                 assert_string_contains(&param_error.reason, "Couldn't open configuration file ");
                 assert_string_contains(&param_error.reason, ". Are you sure it exists?");
             }
@@ -3199,6 +5500,7 @@ mod tests {
         let mut config = BootstrapperConfig::new();
         config.clandestine_port_opt = Some(1234);
         let earning_address = "0x0123456789012345678901234567890123456789";
+        config.earning_wallet = Wallet::new(earning_address);
         let consuming_private_key_text =
             "ABCD00EFABCD00EFABCD00EFABCD00EFABCD00EFABCD00EFABCD00EFABCD00EF";
         let consuming_private_key = PlainData::from_str(consuming_private_key_text).unwrap();
@@ -0,0 +1,327 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use crate::node_configurator::{flushed_write, possible_reader_from_stream};
+use masq_lib::command::StdStreams;
+use rpassword::read_password_with_reader;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+pub const PINENTRY_PROGRAM: &str = "pinentry";
+
+// Where the configurators get the secrets they have to ask for: the parameter is the one the
+// secret would have been given as, like "db-password", if it had been given
+pub trait PromptBackend {
+    fn read_password(
+        &self,
+        parameter: &str,
+        prompt: &str,
+        streams: &mut StdStreams,
+    ) -> Result<String, String>;
+
+    // What was wrong with the last secret, before asking again
+    fn complain(&self, complaint: &str, streams: &mut StdStreams);
+
+    fn attempts(&self) -> usize {
+        3
+    }
+}
+
+// --password-prompt: tty, pinentry, or environment
+pub fn make_prompt_backend(name: &str) -> Box<dyn PromptBackend> {
+    match name {
+        "tty" => Box::new(TtyPromptBackend {}),
+        "pinentry" => Box::new(PinentryPromptBackend::new(PINENTRY_PROGRAM)),
+        "environment" => Box::new(EnvironmentPromptBackend::new()),
+        _ => panic!("Bad clap validation for password-prompt: {}", name),
+    }
+}
+
+pub struct TtyPromptBackend {}
+
+impl PromptBackend for TtyPromptBackend {
+    fn read_password(
+        &self,
+        _parameter: &str,
+        prompt: &str,
+        streams: &mut StdStreams,
+    ) -> Result<String, String> {
+        flushed_write(streams.stdout, prompt);
+        let reader_opt = possible_reader_from_stream(streams);
+        Ok(read_password_with_reader(reader_opt).expect("Fatal error"))
+    }
+
+    fn complain(&self, complaint: &str, streams: &mut StdStreams) {
+        flushed_write(streams.stdout, complaint)
+    }
+}
+
+// Asks in a dialog from a GPG-style pinentry program, which speaks the Assuan protocol on its
+// standard input and output. Complaints wait to be shown in the next dialog.
+pub struct PinentryPromptBackend {
+    program: String,
+    complaint: RefCell<String>,
+}
+
+impl PromptBackend for PinentryPromptBackend {
+    fn read_password(
+        &self,
+        _parameter: &str,
+        prompt: &str,
+        _streams: &mut StdStreams,
+    ) -> Result<String, String> {
+        let complaint = self.complaint.replace(String::new());
+        let error_opt = if complaint.is_empty() {
+            None
+        } else {
+            Some(complaint.as_str())
+        };
+        let mut child = match Command::new(&self.program)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(e) => return Err(format!("Could not start {}: {}", self.program, e)),
+        };
+        let script = pinentry_script(prompt, error_opt);
+        if let Err(e) = child
+            .stdin
+            .take()
+            .expect("pinentry has no stdin")
+            .write_all(script.as_bytes())
+        {
+            return Err(format!("Could not talk to {}: {}", self.program, e));
+        }
+        match child.wait_with_output() {
+            Ok(output) => pin_from_pinentry_output(&String::from_utf8_lossy(&output.stdout)),
+            Err(e) => Err(format!("Could not hear from {}: {}", self.program, e)),
+        }
+    }
+
+    fn complain(&self, complaint: &str, _streams: &mut StdStreams) {
+        let mut pending = self.complaint.borrow_mut();
+        *pending = format!("{} {}", pending, complaint.trim())
+            .trim()
+            .to_string();
+    }
+}
+
+impl PinentryPromptBackend {
+    pub fn new(program: &str) -> Self {
+        Self {
+            program: program.to_string(),
+            complaint: RefCell::new(String::new()),
+        }
+    }
+}
+
+// For installs with nobody at a terminal: MASQ_DB_PASSWORD for db-password, or the first line of
+// the file named by MASQ_DB_PASSWORD_FILE. The answer would be the same every time, so there is
+// only one attempt.
+pub struct EnvironmentPromptBackend {
+    variables: HashMap<String, String>,
+}
+
+impl PromptBackend for EnvironmentPromptBackend {
+    fn read_password(
+        &self,
+        parameter: &str,
+        _prompt: &str,
+        _streams: &mut StdStreams,
+    ) -> Result<String, String> {
+        let name = format!("MASQ_{}", parameter.to_uppercase().replace('-', "_"));
+        let file_name = format!("{}_FILE", name);
+        if let Some(password) = self.variables.get(&name) {
+            return Ok(password.clone());
+        }
+        match self.variables.get(&file_name) {
+            Some(path) => match std::fs::read_to_string(path) {
+                Ok(contents) => Ok(contents.lines().next().unwrap_or("").to_string()),
+                Err(e) => Err(format!("Could not read {} from {}: {}", parameter, path, e)),
+            },
+            None => Err(format!("Neither {} nor {} is set.", name, file_name)),
+        }
+    }
+
+    fn complain(&self, complaint: &str, streams: &mut StdStreams) {
+        flushed_write(streams.stdout, complaint)
+    }
+
+    fn attempts(&self) -> usize {
+        1
+    }
+}
+
+impl EnvironmentPromptBackend {
+    pub fn new() -> Self {
+        Self {
+            variables: std::env::vars().collect(),
+        }
+    }
+}
+
+impl Default for EnvironmentPromptBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn assuan_escape(text: &str) -> String {
+    text.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+fn assuan_unescape(data: &str) -> String {
+    let bytes = data.as_bytes();
+    let mut unescaped = vec![];
+    let mut index = 0;
+    while index < bytes.len() {
+        let escaped_opt = if bytes[index] == b'%' && index + 2 < bytes.len() {
+            std::str::from_utf8(&bytes[index + 1..index + 3])
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        } else {
+            None
+        };
+        match escaped_opt {
+            Some(byte) => {
+                unescaped.push(byte);
+                index += 3;
+            }
+            None => {
+                unescaped.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&unescaped).to_string()
+}
+
+fn pinentry_script(prompt: &str, error_opt: Option<&str>) -> String {
+    let mut script = format!(
+        "SETTITLE MASQ Node\nSETPROMPT {}\n",
+        assuan_escape(prompt.trim())
+    );
+    if let Some(error) = error_opt {
+        script.push_str(&format!("SETERROR {}\n", assuan_escape(error)));
+    }
+    script.push_str("GETPIN\nBYE\n");
+    script
+}
+
+// The PIN comes back in D lines; ERR means the dialog was cancelled or couldn't be shown
+fn pin_from_pinentry_output(output: &str) -> Result<String, String> {
+    let mut pin = String::new();
+    for line in output.lines() {
+        if let Some(data) = line.strip_prefix("D ") {
+            pin.push_str(&assuan_unescape(data));
+        } else if line.starts_with("ERR ") {
+            let message = line.splitn(3, ' ').nth(2).unwrap_or(line);
+            return Err(format!("pinentry: {}", message));
+        }
+    }
+    Ok(pin)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use masq_lib::test_utils::fake_stream_holder::FakeStreamHolder;
+    use masq_lib::test_utils::utils::ensure_node_home_directory_exists;
+    use std::fs::File;
+
+    #[test]
+    fn pinentry_is_asked_for_the_pin_with_any_complaint_about_the_last_one() {
+        let result = pinentry_script(
+            "  Enter password: ",
+            Some("Incorrect password. 100% wrong.\nTry again."),
+        );
+
+        assert_eq!(
+            result,
+            "SETTITLE MASQ Node\n\
+             SETPROMPT Enter password:\n\
+             SETERROR Incorrect password. 100%25 wrong.%0ATry again.\n\
+             GETPIN\n\
+             BYE\n"
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn the_pin_is_unescaped_from_pinentry_output_and_cancellation_is_an_error() {
+        let entered = pin_from_pinentry_output(
+            "OK Pleased to meet you\nOK\nOK\nD pass%25word%0A%zz\nOK\nOK closing connection\n",
+        );
+        let cancelled = pin_from_pinentry_output(
+            "OK Pleased to meet you\nOK\nOK\nERR 83886179 Operation cancelled <Pinentry>\n",
+        );
+        let empty = pin_from_pinentry_output("OK Pleased to meet you\nOK\nOK\nOK\n");
+
+        assert_eq!(entered, Ok("pass%word\n%zz".to_string()));
+        assert_eq!(
+            cancelled,
+            Err("pinentry: Operation cancelled <Pinentry>".to_string())
+        );
+        assert_eq!(empty, Ok(String::new()));
+    }
+
+    #[test]
+    fn pinentry_complaints_are_saved_for_the_next_dialog() {
+        let subject = PinentryPromptBackend::new("/nonexistent/pinentry");
+        let mut holder = FakeStreamHolder::new();
+
+        subject.complain("\nPassphrases do not match.", &mut holder.streams());
+        subject.complain(" Try again.\n", &mut holder.streams());
+        let complaint = subject.complaint.borrow().clone();
+        let result = subject.read_password("db-password", "prompt", &mut holder.streams());
+
+        assert_eq!(
+            complaint,
+            "Passphrases do not match. Try again.".to_string()
+        );
+        assert!(result
+            .unwrap_err()
+            .starts_with("Could not start /nonexistent/pinentry: "));
+        assert_eq!(subject.complaint.borrow().as_str(), "");
+        assert_eq!(holder.stdout.get_string(), String::new());
+    }
+
+    #[test]
+    fn the_environment_backend_reads_a_variable_or_else_a_file() {
+        let home_dir = ensure_node_home_directory_exists(
+            "prompt_backend",
+            "the_environment_backend_reads_a_variable_or_else_a_file",
+        );
+        let path = home_dir.join("passphrase");
+        File::create(&path)
+            .unwrap()
+            .write_all(b"file passphrase\nignored\n")
+            .unwrap();
+        let mut variables = HashMap::new();
+        variables.insert("MASQ_DB_PASSWORD".to_string(), "password".to_string());
+        variables.insert(
+            "MASQ_MNEMONIC_PASSPHRASE_FILE".to_string(),
+            path.to_str().unwrap().to_string(),
+        );
+        let subject = EnvironmentPromptBackend { variables };
+        let mut holder = FakeStreamHolder::new();
+
+        let password = subject.read_password("db-password", "prompt", &mut holder.streams());
+        let passphrase =
+            subject.read_password("mnemonic-passphrase", "prompt", &mut holder.streams());
+        let missing = subject.read_password("booga", "prompt", &mut holder.streams());
+
+        assert_eq!(password, Ok("password".to_string()));
+        assert_eq!(passphrase, Ok("file passphrase".to_string()));
+        assert_eq!(
+            missing,
+            Err("Neither MASQ_BOOGA nor MASQ_BOOGA_FILE is set.".to_string())
+        );
+        assert_eq!(subject.attempts(), 1);
+        assert_eq!(holder.stdout.get_string(), String::new());
+    }
+}
@@ -14,6 +14,7 @@ use crate::stream_messages::*;
 use crate::sub_lib::framer::FramedChunk;
 use crate::sub_lib::framer::Framer;
 use crate::sub_lib::stream_handler_pool::DispatcherNodeQueryResponse;
+use crate::sub_lib::stream_handler_pool::PunchHoleMsg;
 use crate::sub_lib::stream_handler_pool::TransmitDataMsg;
 use crate::test_utils::logging::TestLog;
 use crate::test_utils::recorder::Recorder;
@@ -285,6 +286,7 @@ pub fn make_stream_handler_pool_subs_from(
         remove_sub: recipient!(addr, RemoveStreamMsg),
         bind: recipient!(addr, PoolBindMessage),
         node_query_response: recipient!(addr, DispatcherNodeQueryResponse),
+        punch_hole_sub: recipient!(addr, PunchHoleMsg),
     }
 }
 
@@ -13,6 +13,7 @@ extern "C" {
 }
 
 use crate::bootstrapper::RealUser;
+use crate::sandbox;
 #[cfg(not(target_os = "windows"))]
 use nix::NixPath;
 use std::path::PathBuf;
@@ -63,6 +64,7 @@ pub trait PrivilegeDropper: Send {
     fn drop_privileges(&self, real_user: &RealUser);
     fn chown(&self, file: &PathBuf, real_user: &RealUser);
     fn expect_privilege(&self, privilege_expected: bool) -> bool;
+    fn sandbox(&self) -> Result<String, String>;
 }
 
 pub struct PrivilegeDropperReal {
@@ -151,6 +153,10 @@ impl PrivilegeDropper for PrivilegeDropperReal {
     fn expect_privilege(&self, privilege_expected: bool) -> bool {
         (self.id_wrapper.getuid() == 0) == privilege_expected
     }
+
+    fn sandbox(&self) -> Result<String, String> {
+        sandbox::confine()
+    }
 }
 
 impl PrivilegeDropperReal {
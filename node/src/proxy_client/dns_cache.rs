@@ -0,0 +1,291 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use crate::proxy_client::resolver_wrapper::ResolverWrapper;
+use crate::proxy_client::resolver_wrapper::WrappedLookupIpFuture;
+use crate::sub_lib::metrics::{
+    DNS_CACHE_ENTRIES, DNS_CACHE_EVICTIONS, DNS_CACHE_HITS, DNS_CACHE_MISSES,
+    DNS_CACHE_NEGATIVE_HITS,
+};
+use futures::future;
+use futures::Future;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use trust_dns_resolver::error::{ResolveError, ResolveErrorKind};
+use trust_dns_resolver::lookup_ip::LookupIp;
+use trust_dns_resolver::proto::op::Query;
+
+// How long to remember that a name doesn't exist when the answer didn't say how long it would
+// stay that way
+pub const NEGATIVE_TTL: Duration = Duration::from_secs(60);
+
+#[derive(Clone)]
+enum CachedAnswer {
+    Found(LookupIp),
+    NotFound(Query),
+}
+
+struct CacheEntry {
+    answer: CachedAnswer,
+    valid_until: Instant,
+    last_used: u64,
+}
+
+// Least recently used entries make way for new ones once the cache is full
+struct DnsCache {
+    entries: HashMap<String, CacheEntry>,
+    max_entries: usize,
+    uses: u64,
+}
+
+impl DnsCache {
+    fn new(max_entries: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            max_entries,
+            uses: 0,
+        }
+    }
+
+    fn get(&mut self, host: &str, now: Instant) -> Option<(CachedAnswer, Instant)> {
+        self.uses += 1;
+        let uses = self.uses;
+        match self.entries.get_mut(host) {
+            Some(entry) if entry.valid_until > now => {
+                entry.last_used = uses;
+                return Some((entry.answer.clone(), entry.valid_until));
+            }
+            Some(_) => {}
+            None => return None,
+        }
+        self.entries.remove(host);
+        DNS_CACHE_ENTRIES.set(self.entries.len() as u64);
+        None
+    }
+
+    fn insert(&mut self, host: String, answer: CachedAnswer, valid_until: Instant, now: Instant) {
+        if valid_until <= now {
+            return;
+        }
+        if !self.entries.contains_key(&host) && self.entries.len() >= self.max_entries {
+            self.entries.retain(|_, entry| entry.valid_until > now);
+        }
+        while !self.entries.contains_key(&host) && self.entries.len() >= self.max_entries {
+            let least_recently_used = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(host, _)| host.clone())
+                .expect("Full DNS cache is empty");
+            self.entries.remove(&least_recently_used);
+            DNS_CACHE_EVICTIONS.add(1);
+        }
+        self.uses += 1;
+        self.entries.insert(
+            host,
+            CacheEntry {
+                answer,
+                valid_until,
+                last_used: self.uses,
+            },
+        );
+        DNS_CACHE_ENTRIES.set(self.entries.len() as u64);
+    }
+}
+
+// Answers repeated look-ups for the same host from memory until the answer's TTL runs out,
+// including answers that say the host doesn't exist
+pub struct CachingResolverWrapper {
+    delegate: Box<dyn ResolverWrapper>,
+    cache: Arc<Mutex<DnsCache>>,
+}
+
+impl ResolverWrapper for CachingResolverWrapper {
+    fn lookup_ip(&self, host: &str) -> Box<WrappedLookupIpFuture> {
+        let key = host.trim_end_matches('.').to_lowercase();
+        let cached_opt = self
+            .cache
+            .lock()
+            .expect("DNS cache is poisoned")
+            .get(&key, Instant::now());
+        match cached_opt {
+            Some((CachedAnswer::Found(lookup_ip), _)) => {
+                DNS_CACHE_HITS.add(1);
+                return Box::new(future::ok(lookup_ip));
+            }
+            Some((CachedAnswer::NotFound(query), valid_until)) => {
+                DNS_CACHE_NEGATIVE_HITS.add(1);
+                return Box::new(future::err(
+                    ResolveErrorKind::NoRecordsFound {
+                        query,
+                        valid_until: Some(valid_until),
+                    }
+                    .into(),
+                ));
+            }
+            None => DNS_CACHE_MISSES.add(1),
+        }
+        let cache = self.cache.clone();
+        Box::new(self.delegate.lookup_ip(host).then(move |result| {
+            let now = Instant::now();
+            let mut cache = cache.lock().expect("DNS cache is poisoned");
+            match &result {
+                Ok(lookup_ip) => cache.insert(
+                    key,
+                    CachedAnswer::Found(lookup_ip.clone()),
+                    lookup_ip.valid_until(),
+                    now,
+                ),
+                Err(e) => {
+                    if let Some((query, valid_until)) = not_found(e, now) {
+                        cache.insert(key, CachedAnswer::NotFound(query), valid_until, now)
+                    }
+                }
+            }
+            result
+        }))
+    }
+}
+
+impl CachingResolverWrapper {
+    pub fn new(delegate: Box<dyn ResolverWrapper>, max_entries: usize) -> Self {
+        Self {
+            delegate,
+            cache: Arc::new(Mutex::new(DnsCache::new(max_entries))),
+        }
+    }
+}
+
+// Only an answer that the host doesn't exist is worth remembering; other failures, like time-outs,
+// may not happen next time
+fn not_found(error: &ResolveError, now: Instant) -> Option<(Query, Instant)> {
+    match error.kind() {
+        ResolveErrorKind::NoRecordsFound { query, valid_until } => Some((
+            query.clone(),
+            valid_until.unwrap_or_else(|| now + NEGATIVE_TTL),
+        )),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy_client::local_test_utils::ResolverWrapperMock;
+    use std::net::IpAddr;
+    use std::str::FromStr;
+    use trust_dns_resolver::proto::rr::{Name, RecordType};
+
+    fn no_records_found(host: &str) -> ResolveError {
+        ResolveErrorKind::NoRecordsFound {
+            query: Query::query(Name::from_str(host).unwrap(), RecordType::A),
+            valid_until: None,
+        }
+        .into()
+    }
+
+    fn addresses(lookup_ip: LookupIp) -> Vec<IpAddr> {
+        lookup_ip.iter().collect()
+    }
+
+    #[test]
+    fn repeated_look_ups_are_answered_from_the_cache() {
+        let lookup_ip_params_arc = Arc::new(Mutex::new(vec![]));
+        let ip_addr = IpAddr::from_str("1.2.3.4").unwrap();
+        let delegate = ResolverWrapperMock::new()
+            .lookup_ip_parameters(&lookup_ip_params_arc)
+            .lookup_ip_success(vec![ip_addr])
+            .lookup_ip_failure(no_records_found("nonexistent.com."));
+        let subject = CachingResolverWrapper::new(Box::new(delegate), 10);
+        let hits_before = DNS_CACHE_HITS.get();
+        let negative_hits_before = DNS_CACHE_NEGATIVE_HITS.get();
+
+        let first = subject.lookup_ip("example.com").wait().unwrap();
+        let second = subject.lookup_ip("EXAMPLE.com.").wait().unwrap();
+        let first_missing = subject.lookup_ip("nonexistent.com").wait();
+        let second_missing = subject.lookup_ip("nonexistent.com").wait();
+
+        assert_eq!(addresses(first), vec![ip_addr]);
+        assert_eq!(addresses(second), vec![ip_addr]);
+        match (
+            first_missing.err().unwrap().kind(),
+            second_missing.err().unwrap().kind(),
+        ) {
+            (
+                ResolveErrorKind::NoRecordsFound { .. },
+                ResolveErrorKind::NoRecordsFound { query, valid_until },
+            ) => {
+                assert_eq!(query.name(), &Name::from_str("nonexistent.com.").unwrap());
+                assert!(valid_until.unwrap() <= Instant::now() + NEGATIVE_TTL);
+            }
+            kinds => panic!("Expected NoRecordsFound twice, got {:?}", kinds),
+        }
+        assert_eq!(
+            *lookup_ip_params_arc.lock().unwrap(),
+            vec!["example.com".to_string(), "nonexistent.com".to_string()]
+        );
+        assert!(DNS_CACHE_HITS.get() > hits_before);
+        assert!(DNS_CACHE_NEGATIVE_HITS.get() > negative_hits_before);
+    }
+
+    #[test]
+    fn failures_other_than_nonexistence_are_not_cached() {
+        let lookup_ip_params_arc = Arc::new(Mutex::new(vec![]));
+        let ip_addr = IpAddr::from_str("2.3.4.5").unwrap();
+        let delegate = ResolverWrapperMock::new()
+            .lookup_ip_parameters(&lookup_ip_params_arc)
+            .lookup_ip_failure(ResolveErrorKind::Timeout.into())
+            .lookup_ip_success(vec![ip_addr]);
+        let subject = CachingResolverWrapper::new(Box::new(delegate), 10);
+
+        let first = subject.lookup_ip("slow.com").wait();
+        let second = subject.lookup_ip("slow.com").wait().unwrap();
+
+        assert!(first.is_err());
+        assert_eq!(addresses(second), vec![ip_addr]);
+        assert_eq!(lookup_ip_params_arc.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn expired_entries_are_looked_up_again_and_full_caches_evict_the_least_recently_used() {
+        let now = Instant::now();
+        let query = Query::default();
+        let mut subject = DnsCache::new(2);
+        subject.insert(
+            "stale.com".to_string(),
+            CachedAnswer::NotFound(query.clone()),
+            now + Duration::from_millis(1),
+            now,
+        );
+        subject.insert(
+            "old.com".to_string(),
+            CachedAnswer::NotFound(query.clone()),
+            now + NEGATIVE_TTL,
+            now,
+        );
+        let stale = subject.get("stale.com", now + Duration::from_secs(1));
+        subject.insert(
+            "recent.com".to_string(),
+            CachedAnswer::NotFound(query.clone()),
+            now + NEGATIVE_TTL,
+            now,
+        );
+        subject.get("old.com", now);
+        subject.insert(
+            "new.com".to_string(),
+            CachedAnswer::NotFound(query.clone()),
+            now + NEGATIVE_TTL,
+            now,
+        );
+        subject.insert(
+            "expired.com".to_string(),
+            CachedAnswer::NotFound(query),
+            now,
+            now,
+        );
+
+        assert!(stale.is_none());
+        let mut hosts = subject.entries.keys().cloned().collect::<Vec<String>>();
+        hosts.sort();
+        assert_eq!(hosts, vec!["new.com".to_string(), "old.com".to_string()]);
+    }
+}
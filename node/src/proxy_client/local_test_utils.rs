@@ -6,6 +6,7 @@ use crate::proxy_client::resolver_wrapper::WrappedLookupIpFuture;
 use futures::future;
 use futures::sync::mpsc::unbounded;
 use futures::sync::mpsc::SendError;
+use masq_lib::secure_dns::SecureDnsServer;
 use std::cell::RefCell;
 use std::net::IpAddr;
 use std::sync::Arc;
@@ -80,6 +81,7 @@ impl ResolverWrapperMock {
 pub struct ResolverWrapperFactoryMock {
     factory_results: RefCell<Vec<Box<dyn ResolverWrapper>>>,
     factory_parameters: RefCell<Arc<Mutex<Vec<(ResolverConfig, ResolverOpts)>>>>,
+    make_secure_parameters: Arc<Mutex<Vec<(Vec<SecureDnsServer>, usize)>>>,
 }
 
 impl ResolverWrapperFactory for ResolverWrapperFactoryMock {
@@ -89,6 +91,18 @@ impl ResolverWrapperFactory for ResolverWrapperFactoryMock {
         parameters_guard.push((config, options));
         self.factory_results.borrow_mut().remove(0)
     }
+
+    fn make_secure(
+        &self,
+        servers: Vec<SecureDnsServer>,
+        threads: usize,
+    ) -> Box<dyn ResolverWrapper> {
+        self.make_secure_parameters
+            .lock()
+            .unwrap()
+            .push((servers, threads));
+        self.factory_results.borrow_mut().remove(0)
+    }
 }
 
 impl ResolverWrapperFactoryMock {
@@ -96,6 +110,7 @@ impl ResolverWrapperFactoryMock {
         ResolverWrapperFactoryMock {
             factory_results: RefCell::new(vec![]),
             factory_parameters: RefCell::new(Arc::new(Mutex::new(vec![]))),
+            make_secure_parameters: Arc::new(Mutex::new(vec![])),
         }
     }
 
@@ -111,6 +126,14 @@ impl ResolverWrapperFactoryMock {
         *parameters = self.factory_parameters.borrow_mut().clone();
         self
     }
+
+    pub fn make_secure_parameters(
+        mut self,
+        parameters: &Arc<Mutex<Vec<(Vec<SecureDnsServer>, usize)>>>,
+    ) -> ResolverWrapperFactoryMock {
+        self.make_secure_parameters = parameters.clone();
+        self
+    }
 }
 
 pub fn make_send_error<T>(msg: T) -> Result<(), SendError<T>> {
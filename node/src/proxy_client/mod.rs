@@ -1,44 +1,75 @@
 // Copyright (c) 2017-2019, Substratum LLC (https://substratum.net) and/or its affiliates. All rights reserved.
 
+mod dns_cache;
 #[cfg(test)]
 mod local_test_utils;
+pub mod protocol_pack;
 mod resolver_wrapper;
+pub mod secure_resolver;
+pub mod smtp_protocol_pack;
 mod stream_establisher;
 mod stream_handler_pool;
 mod stream_reader;
 mod stream_writer;
 
+use crate::proxy_client::dns_cache::CachingResolverWrapper;
+use crate::proxy_client::protocol_pack::{ExitProtocolPack, ProtocolPackRegistry};
+use crate::proxy_client::resolver_wrapper::ResolverWrapper;
 use crate::proxy_client::resolver_wrapper::ResolverWrapperFactory;
 use crate::proxy_client::resolver_wrapper::ResolverWrapperFactoryReal;
 use crate::proxy_client::stream_handler_pool::StreamHandlerPool;
 use crate::proxy_client::stream_handler_pool::StreamHandlerPoolFactory;
 use crate::proxy_client::stream_handler_pool::StreamHandlerPoolFactoryReal;
-use crate::sub_lib::accountant::ReportExitServiceProvidedMessage;
+use crate::proxy_client::stream_reader::ReadGates;
+use crate::sub_lib::accountant::{PaymentOverdue_0v1, ReportExitServiceProvidedMessage};
 use crate::sub_lib::cryptde::CryptDE;
 use crate::sub_lib::cryptde::PublicKey;
+use crate::sub_lib::data_cap;
+use crate::sub_lib::data_cap::DataCapStatus;
 use crate::sub_lib::hopper::MessageType;
 use crate::sub_lib::hopper::{ExpiredCoresPackage, IncipientCoresPackage};
-use crate::sub_lib::logger::Logger;
+use crate::sub_lib::logger::{with_correlation_id, Logger};
+use crate::sub_lib::metrics;
 use crate::sub_lib::peer_actors::BindMessage;
 use crate::sub_lib::proxy_client::InboundServerData;
 use crate::sub_lib::proxy_client::ProxyClientConfig;
 use crate::sub_lib::proxy_client::ProxyClientSubs;
 use crate::sub_lib::proxy_client::{ClientResponsePayload_0v1, DnsResolveFailure_0v1};
-use crate::sub_lib::proxy_server::ClientRequestPayload_0v1;
+use crate::sub_lib::proxy_client::{
+    ClientUdpResponsePayload_0v1, InboundServerDatagram, StreamsQuery, UDP_IDLE_TIMEOUT,
+};
+use crate::sub_lib::proxy_server::{
+    ClientRequestPayload_0v1, ClientUdpRequestPayload_0v1, StreamAckPayload_0v1,
+};
+use crate::sub_lib::redacted::Redacted;
 use crate::sub_lib::route::Route;
 use crate::sub_lib::sequence_buffer::SequencedPacket;
+use crate::sub_lib::sequence_window::{
+    Retransmission, SendWindow, MAX_RETRANSMISSIONS, PAUSE_READING_BYTES, RESUME_READING_BYTES,
+    RETRANSMIT_CHECK_INTERVAL, SEND_WINDOW_SIZE,
+};
 use crate::sub_lib::stream_key::StreamKey;
+use crate::sub_lib::stream_stats::StreamStats;
+use crate::sub_lib::ttl_hashmap::TtlHashMap;
 use crate::sub_lib::utils::NODE_MAILBOX_CAPACITY;
 use crate::sub_lib::versioned_data::VersionedData;
 use crate::sub_lib::wallet::Wallet;
 use actix::Actor;
 use actix::Addr;
+use actix::AsyncContext;
 use actix::Context;
 use actix::Handler;
 use actix::Recipient;
+use masq_lib::exit_policy::ExitPolicy;
+use masq_lib::messages::{ToMessageBody, UiStream, UiStreamsResponse};
+use masq_lib::secure_dns::SecureDnsServer;
+use masq_lib::ui_gateway::{MessageTarget, NodeToUiMessage};
 use pretty_hex::PrettyHex;
 use std::collections::HashMap;
 use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
 use trust_dns_resolver::config::NameServerConfig;
 use trust_dns_resolver::config::Protocol;
 use trust_dns_resolver::config::ResolverConfig;
@@ -48,13 +79,26 @@ pub const CRASH_KEY: &str = "PROXYCLIENT";
 
 pub struct ProxyClient {
     dns_servers: Vec<SocketAddr>,
+    secure_dns_servers: Vec<SecureDnsServer>,
+    dns_cache_size: usize,
+    dns_lookup_threads: usize,
+    exit_policy: ExitPolicy,
+    stream_idle_timeout_opt: Option<Duration>,
+    protocol_packs: Arc<ProtocolPackRegistry>,
     resolver_wrapper_factory: Box<dyn ResolverWrapperFactory>,
     stream_handler_pool_factory: Box<dyn StreamHandlerPoolFactory>,
     cryptde: &'static dyn CryptDE,
     to_hopper: Option<Recipient<IncipientCoresPackage>>,
     to_accountant: Option<Recipient<ReportExitServiceProvidedMessage>>,
+    to_ui_gateway: Option<Recipient<NodeToUiMessage>>,
     pool: Option<Box<dyn StreamHandlerPool>>,
     stream_contexts: HashMap<StreamKey, StreamContext>,
+    send_windows: HashMap<StreamKey, SendWindow>,
+    send_window_size: usize,
+    // Shared with the StreamReaders, so that a stream whose window is backed up stops reading
+    read_gates: ReadGates,
+    served_streams: HashMap<StreamKey, StreamStats>,
+    udp_contexts: TtlHashMap<StreamKey, StreamContext>,
     exit_service_rate: u64,
     exit_byte_rate: u64,
     logger: Logger,
@@ -72,17 +116,34 @@ impl Handler<BindMessage> for ProxyClient {
         ctx.set_mailbox_capacity(NODE_MAILBOX_CAPACITY);
         self.to_hopper = Some(msg.peer_actors.hopper.from_hopper_client);
         self.to_accountant = Some(msg.peer_actors.accountant.report_exit_service_provided);
-        let mut config = ResolverConfig::new();
-        for dns_server_ref in &self.dns_servers {
-            info!(self.logger, "Adding DNS server: {}", dns_server_ref.ip());
-            config.add_name_server(NameServerConfig {
-                socket_addr: *dns_server_ref,
-                protocol: Protocol::Udp,
-                tls_dns_name: None,
-            })
-        }
-        let opts = ResolverOpts::default();
-        let resolver = self.resolver_wrapper_factory.make(config, opts);
+        self.to_ui_gateway = Some(msg.peer_actors.ui_gateway.node_to_ui_message_sub.clone());
+        let resolver = if self.secure_dns_servers.is_empty() {
+            let mut config = ResolverConfig::new();
+            for dns_server_ref in &self.dns_servers {
+                info!(self.logger, "Adding DNS server: {}", dns_server_ref.ip());
+                config.add_name_server(NameServerConfig {
+                    socket_addr: *dns_server_ref,
+                    protocol: Protocol::Udp,
+                    tls_dns_name: None,
+                })
+            }
+            let opts = ResolverOpts::default();
+            self.resolver_wrapper_factory.make(config, opts)
+        } else {
+            for secure_dns_server_ref in &self.secure_dns_servers {
+                info!(
+                    self.logger,
+                    "Adding secure DNS server: {}", secure_dns_server_ref
+                );
+            }
+            self.resolver_wrapper_factory
+                .make_secure(self.secure_dns_servers.clone(), self.dns_lookup_threads)
+        };
+        let resolver: Box<dyn ResolverWrapper> = if self.dns_cache_size > 0 {
+            Box::new(CachingResolverWrapper::new(resolver, self.dns_cache_size))
+        } else {
+            resolver
+        };
         self.pool = Some(self.stream_handler_pool_factory.make(
             resolver,
             self.cryptde,
@@ -90,7 +151,13 @@ impl Handler<BindMessage> for ProxyClient {
             msg.peer_actors.proxy_client,
             self.exit_service_rate,
             self.exit_byte_rate,
+            self.stream_idle_timeout_opt,
+            self.protocol_packs.clone(),
+            self.read_gates.clone(),
         ));
+        ctx.run_interval(RETRANSMIT_CHECK_INTERVAL, |proxy_client, _ctx| {
+            proxy_client.retransmit_overdue_packets(Instant::now())
+        });
     }
 }
 
@@ -102,32 +169,59 @@ impl Handler<ExpiredCoresPackage<ClientRequestPayload_0v1>> for ProxyClient {
         msg: ExpiredCoresPackage<ClientRequestPayload_0v1>,
         _ctx: &mut Self::Context,
     ) -> Self::Result {
-        let is_zero_hop = match msg.remaining_route.next_hop(self.cryptde) {
-            Ok(live_hop) => &live_hop.public_key == self.cryptde.public_key(),
-            Err(_) => false,
-        };
+        with_correlation_id(msg.payload.stream_key.to_string(), || {
+            self.handle_client_request_payload(msg)
+        })
+    }
+}
+
+impl Handler<ExpiredCoresPackage<ClientUdpRequestPayload_0v1>> for ProxyClient {
+    type Result = ();
+
+    fn handle(
+        &mut self,
+        msg: ExpiredCoresPackage<ClientUdpRequestPayload_0v1>,
+        _ctx: &mut Self::Context,
+    ) -> Self::Result {
+        let is_zero_hop = self.is_zero_hop(&msg.remaining_route);
         let payload = msg.payload;
+        if !is_zero_hop
+            && (self.is_denied_by_exit_policy(
+                &payload.stream_key,
+                Some(&payload.target_hostname),
+                payload.target_port,
+            ) || self.is_refused_by_data_cap(
+                &payload.stream_key,
+                self.udp_contexts.get(&payload.stream_key).is_none(),
+            ))
+        {
+            return;
+        }
         let paying_wallet = msg.paying_wallet;
         if paying_wallet.is_some() || is_zero_hop {
-            let pool = self.pool.as_mut().expect("StreamHandlerPool unbound");
-            let return_route = msg.remaining_route;
-            let latest_stream_context = StreamContext {
-                return_route,
-                payload_destination_key: payload.originator_public_key.clone(),
-                paying_wallet: paying_wallet.clone(),
-            };
             debug!(
                 self.logger,
-                "Received ClientRequestPayload: stream {}, sequence {}, length {}",
+                "Received ClientUdpRequestPayload: stream {}, length {}, for {}:{}",
                 payload.stream_key,
-                payload.sequenced_packet.sequence_number,
-                payload.sequenced_packet.data.len()
+                payload.data.len(),
+                payload.target_hostname,
+                payload.target_port
             );
-            self.stream_contexts
-                .insert(payload.stream_key, latest_stream_context);
-            pool.process_package(payload, paying_wallet);
+            self.udp_contexts.insert(
+                payload.stream_key,
+                StreamContext {
+                    return_route: msg.remaining_route,
+                    alternate_return_route_opt: None,
+                    payload_destination_key: payload.originator_public_key.clone(),
+                    paying_wallet: paying_wallet.clone(),
+                },
+            );
+            self.pool
+                .as_mut()
+                .expect("StreamHandlerPool unbound")
+                .process_udp_package(payload, paying_wallet);
         } else {
-            warning!(self.logger, "Refusing to provide exit services for CORES package with {}-byte payload without paying wallet", payload.sequenced_packet.data.len());
+            warning!(self.logger, "Refusing to provide exit services for CORES package with {}-byte UDP payload without paying wallet", payload.data.len());
         }
     }
 }
@@ -141,33 +235,171 @@ impl Handler<InboundServerData> for ProxyClient {
         let msg_sequence_number = msg.sequence_number;
         let msg_last_data = msg.last_data;
         let msg_stream_key = msg.stream_key;
-        let stream_context = match self.stream_contexts.get(&msg.stream_key) {
+        let is_retiring = self
+            .send_windows
+            .get(&msg_stream_key)
+            .map(|window| window.last_data_offered())
+            .unwrap_or(false);
+        if is_retiring || !self.stream_contexts.contains_key(&msg_stream_key) {
+            error!(
+                self.logger,
+                "Received InboundServerData{} from {}: stream {}, sequence {}, length {}; but no such known stream - ignoring\n{}",
+                if msg_last_data {" (last_data)"} else {""},
+                msg_source,
+                msg_stream_key,
+                msg_sequence_number,
+                msg_data_len,
+                msg.data.hex_dump().to_string(),
+            );
+            return;
+        }
+        if let Some(stats) = self.served_streams.get_mut(&msg_stream_key) {
+            stats.bytes_in += msg_data_len as u64;
+        }
+        let send_window_size = self.send_window_size;
+        let sendable = self
+            .send_windows
+            .entry(msg_stream_key)
+            .or_insert_with(|| SendWindow::new(send_window_size))
+            .offer(
+                SequencedPacket::new(msg.data, msg_sequence_number, msg_last_data),
+                Instant::now(),
+            );
+        if sendable.is_empty() {
+            debug!(
+                self.logger,
+                "Holding packet {} of stream {} until the exit window opens",
+                msg_sequence_number,
+                msg_stream_key
+            );
+        }
+        self.apply_backpressure(&msg_stream_key);
+        self.send_new_responses(&msg_stream_key, sendable);
+        if msg_last_data {
+            debug!(
+                self.logger,
+                "Retiring stream key {}: no more data once the rest is acknowledged",
+                msg_stream_key
+            );
+        }
+    }
+}
+
+impl Handler<ExpiredCoresPackage<StreamAckPayload_0v1>> for ProxyClient {
+    type Result = ();
+
+    fn handle(
+        &mut self,
+        msg: ExpiredCoresPackage<StreamAckPayload_0v1>,
+        _ctx: &mut Self::Context,
+    ) -> Self::Result {
+        let stream_key = msg.payload.stream_key;
+        let next_sequence_number = msg.payload.next_sequence_number;
+        let sendable = match self.send_windows.get_mut(&stream_key) {
+            Some(window) => window.acknowledge(next_sequence_number, Instant::now()),
+            None => {
+                debug!(
+                    self.logger,
+                    "Ignoring acknowledgment through sequence {} for unknown stream {}",
+                    next_sequence_number,
+                    stream_key
+                );
+                return;
+            }
+        };
+        debug!(
+            self.logger,
+            "Stream {} acknowledged before sequence {}; sending {} more",
+            stream_key,
+            next_sequence_number,
+            sendable.len()
+        );
+        self.apply_backpressure(&stream_key);
+        self.send_new_responses(&stream_key, sendable);
+    }
+}
+
+impl Handler<StreamsQuery> for ProxyClient {
+    type Result = ();
+
+    fn handle(&mut self, msg: StreamsQuery, _ctx: &mut Self::Context) -> Self::Result {
+        let now = Instant::now();
+        let mut served = self.served_streams.iter().collect::<Vec<_>>();
+        served.sort_by_key(|(_, stats)| stats.opened_at);
+        let serving = served
+            .into_iter()
+            .map(|(stream_key, stats)| stats.to_ui_stream(stream_key, now))
+            .collect::<Vec<UiStream>>();
+        let body = UiStreamsResponse {
+            consuming: msg.consuming,
+            serving,
+        }
+        .tmb(msg.context_id);
+        self.to_ui_gateway
+            .as_ref()
+            .expect("UiGateway is unbound")
+            .try_send(NodeToUiMessage {
+                target: MessageTarget::ClientId(msg.client_id),
+                body,
+            })
+            .expect("UiGateway is dead");
+    }
+}
+
+impl Handler<InboundServerDatagram> for ProxyClient {
+    type Result = ();
+
+    fn handle(&mut self, msg: InboundServerDatagram, _ctx: &mut Self::Context) -> Self::Result {
+        let stream_context = match self.udp_contexts.get(&msg.stream_key) {
             Some(sc) => sc,
             None => {
-                error!(
+                debug!(
                     self.logger,
-                    "Received InboundServerData{} from {}: stream {}, sequence {}, length {}; but no such known stream - ignoring\n{}",
-                    if msg_last_data {" (last_data)"} else {""},
-                    msg_source,
-                    msg_stream_key,
-                    msg_sequence_number,
-                    msg_data_len,
-                    msg.data.hex_dump().to_string(),
+                    "Received {}-byte datagram from {} for stream {} after it went idle - ignoring",
+                    msg.data.len(),
+                    msg.source,
+                    msg.stream_key
                 );
                 return;
             }
         };
-        if self.send_response_to_hopper(msg, &stream_context).is_err() {
-            return;
+        let msg_data_len = msg.data.len();
+        let source = msg.source;
+        let payload = ClientUdpResponsePayload_0v1 {
+            stream_key: msg.stream_key,
+            source,
+            data: msg.data.into(),
         };
-        self.report_response_exit_to_accountant(&stream_context, msg_data_len);
-        if msg_last_data {
-            debug!(
-                self.logger,
-                "Retiring stream key {}: no more data", msg_stream_key
-            );
-            self.stream_contexts.remove(&msg_stream_key);
+        debug!(
+            self.logger,
+            "Sending ClientUdpResponsePayload to Hopper: stream {}, length {}",
+            payload.stream_key,
+            msg_data_len
+        );
+        match IncipientCoresPackage::new(
+            self.cryptde,
+            stream_context.return_route.clone(),
+            payload.into(),
+            &stream_context.payload_destination_key,
+        ) {
+            Ok(icp) => self
+                .to_hopper
+                .as_ref()
+                .expect("Hopper unbound")
+                .try_send(icp)
+                .expect("Hopper is dead"),
+            Err(err) => {
+                error!(
+                    self.logger,
+                    "Could not create CORES package for {}-byte datagram from {}: {} - ignoring",
+                    msg_data_len,
+                    source,
+                    err
+                );
+                return;
+            }
         }
+        self.report_response_exit_to_accountant(&stream_context, msg_data_len);
     }
 }
 
@@ -198,7 +430,7 @@ impl Handler<DnsResolveFailure_0v1> for ProxyClient {
                     self.logger,
                     "Removing stream key {} for DnsResolveFailure", stream_key
                 );
-                self.stream_contexts.remove(&stream_key);
+                self.retire_stream(&stream_key);
             }
             None => error!(
                 self.logger,
@@ -208,6 +440,42 @@ impl Handler<DnsResolveFailure_0v1> for ProxyClient {
     }
 }
 
+// An exit Node doesn't know who its consumers are; the only way back to a delinquent one is the
+// return route of a stream it's paying for
+impl Handler<PaymentOverdue_0v1> for ProxyClient {
+    type Result = ();
+
+    fn handle(&mut self, msg: PaymentOverdue_0v1, _ctx: &mut Self::Context) -> Self::Result {
+        let stream_context = match self
+            .stream_contexts
+            .values()
+            .find(|context| context.paying_wallet.as_ref() == Some(&msg.debtor))
+        {
+            Some(stream_context) => stream_context,
+            None => {
+                debug!(
+                    self.logger,
+                    "No open stream to send a payment-overdue notice for {} over",
+                    Redacted(&msg.debtor)
+                );
+                return;
+            }
+        };
+        let package = IncipientCoresPackage::new(
+            self.cryptde,
+            stream_context.return_route.clone(),
+            MessageType::PaymentOverdue(msg.into()),
+            &stream_context.payload_destination_key,
+        )
+        .expect("Failed to create IncipientCoresPackage");
+        self.to_hopper
+            .as_ref()
+            .expect("Hopper is unbound")
+            .try_send(package)
+            .expect("Hopper is dead");
+    }
+}
+
 impl ProxyClient {
     pub fn new(config: ProxyClientConfig) -> ProxyClient {
         if config.dns_servers.is_empty() {
@@ -215,19 +483,38 @@ impl ProxyClient {
         }
         ProxyClient {
             dns_servers: config.dns_servers,
+            secure_dns_servers: config.secure_dns_servers,
+            dns_cache_size: config.dns_cache_size,
+            dns_lookup_threads: config.dns_lookup_threads,
+            exit_policy: config.exit_policy,
+            stream_idle_timeout_opt: config.stream_idle_timeout_opt,
+            protocol_packs: Arc::new(ProtocolPackRegistry::new()),
             resolver_wrapper_factory: Box::new(ResolverWrapperFactoryReal {}),
             stream_handler_pool_factory: Box::new(StreamHandlerPoolFactoryReal {}),
             cryptde: config.cryptde,
             to_hopper: None,
             to_accountant: None,
+            to_ui_gateway: None,
             pool: None,
             stream_contexts: HashMap::new(),
+            send_windows: HashMap::new(),
+            send_window_size: SEND_WINDOW_SIZE,
+            read_gates: ReadGates::new(),
+            served_streams: HashMap::new(),
+            udp_contexts: TtlHashMap::new(UDP_IDLE_TIMEOUT),
             exit_service_rate: config.exit_service_rate,
             exit_byte_rate: config.exit_byte_rate,
             logger: Logger::new("ProxyClient"),
         }
     }
 
+    // Packs have to be registered before the ProxyClient is bound, because its streams share them
+    pub fn register_protocol_pack(&mut self, pack: Box<dyn ExitProtocolPack>) {
+        Arc::get_mut(&mut self.protocol_packs)
+            .expect("Protocol packs are in use: register them before binding the ProxyClient")
+            .register(pack);
+    }
+
     pub fn make_subs_from(addr: &Addr<ProxyClient>) -> ProxyClientSubs {
         ProxyClientSubs {
             bind: addr.clone().recipient::<BindMessage>(),
@@ -236,45 +523,329 @@ impl ProxyClient {
                 .recipient::<ExpiredCoresPackage<ClientRequestPayload_0v1>>(),
             inbound_server_data: addr.clone().recipient::<InboundServerData>(),
             dns_resolve_failed: addr.clone().recipient::<DnsResolveFailure_0v1>(),
+            udp_from_hopper: addr
+                .clone()
+                .recipient::<ExpiredCoresPackage<ClientUdpRequestPayload_0v1>>(),
+            inbound_server_datagram: addr.clone().recipient::<InboundServerDatagram>(),
+            stream_ack_from_hopper: addr
+                .clone()
+                .recipient::<ExpiredCoresPackage<StreamAckPayload_0v1>>(),
+            streams_query: addr.clone().recipient::<StreamsQuery>(),
+            payment_overdue: addr.clone().recipient::<PaymentOverdue_0v1>(),
+        }
+    }
+
+    fn handle_client_request_payload(
+        &mut self,
+        msg: ExpiredCoresPackage<ClientRequestPayload_0v1>,
+    ) {
+        let is_zero_hop = self.is_zero_hop(&msg.remaining_route);
+        let payload = msg.payload;
+        if !is_zero_hop
+            && (self.is_denied_by_exit_policy(
+                &payload.stream_key,
+                payload.target_hostname.as_deref(),
+                payload.target_port,
+            ) || self.is_refused_by_data_cap(
+                &payload.stream_key,
+                !self.stream_contexts.contains_key(&payload.stream_key),
+            ))
+        {
+            return;
+        }
+        let paying_wallet = msg.paying_wallet;
+        if paying_wallet.is_some() || is_zero_hop {
+            let is_open = self.stream_contexts.contains_key(&payload.stream_key);
+            if payload.is_resumption && !is_open {
+                self.refuse_resumption(
+                    &payload.stream_key,
+                    msg.remaining_route,
+                    payload.originator_public_key,
+                );
+                return;
+            }
+            let latest_stream_context = if payload.is_resumption {
+                // The routes the stream had before died with the originating Node's last run
+                info!(
+                    self.logger,
+                    "Resuming stream {} at sequence {} for a restarted originating Node",
+                    payload.stream_key,
+                    payload.sequenced_packet.sequence_number
+                );
+                StreamContext {
+                    return_route: msg.remaining_route,
+                    alternate_return_route_opt: None,
+                    payload_destination_key: payload.originator_public_key.clone(),
+                    paying_wallet: paying_wallet.clone(),
+                }
+            } else {
+                self.updated_stream_context(
+                    &payload.stream_key,
+                    msg.remaining_route,
+                    payload.originator_public_key.clone(),
+                    paying_wallet.clone(),
+                )
+            };
+            debug!(
+                self.logger,
+                "Received ClientRequestPayload: stream {}, sequence {}, length {}",
+                payload.stream_key,
+                payload.sequenced_packet.sequence_number,
+                payload.sequenced_packet.data.len()
+            );
+            self.stream_contexts
+                .insert(payload.stream_key, latest_stream_context);
+            metrics::SERVED_STREAMS.set(self.stream_contexts.len() as u64);
+            self.served_streams
+                .entry(payload.stream_key)
+                .or_insert_with(|| {
+                    StreamStats::new(
+                        payload.protocol,
+                        payload.target_hostname.clone(),
+                        Instant::now(),
+                    )
+                })
+                .bytes_out += payload.sequenced_packet.data.len() as u64;
+            let pool = self.pool.as_mut().expect("StreamHandlerPool unbound");
+            pool.process_package(payload, paying_wallet);
+        } else {
+            warning!(self.logger, "Refusing to provide exit services for CORES package with {}-byte payload without paying wallet", payload.sequenced_packet.data.len());
+        }
+    }
+
+    // An empty last packet tells the originating Node to start the stream over, rather than wait
+    // for a server this Node no longer has a connection to
+    fn refuse_resumption(
+        &self,
+        stream_key: &StreamKey,
+        return_route: Route,
+        originator_public_key: PublicKey,
+    ) {
+        info!(
+            self.logger,
+            "Can't resume stream {}: it's no longer open here", stream_key
+        );
+        let stream_context = StreamContext {
+            return_route,
+            alternate_return_route_opt: None,
+            payload_destination_key: originator_public_key,
+            paying_wallet: None,
+        };
+        let _ = self.send_response_to_hopper(
+            stream_key,
+            SequencedPacket::new(vec![], 0, true),
+            &stream_context,
+            false,
+        );
+    }
+
+    fn is_zero_hop(&self, remaining_route: &Route) -> bool {
+        match remaining_route.next_hop(self.cryptde) {
+            Ok(live_hop) => &live_hop.public_key == self.cryptde.public_key(),
+            Err(_) => false,
+        }
+    }
+
+    fn is_denied_by_exit_policy(
+        &self,
+        stream_key: &StreamKey,
+        hostname_opt: Option<&str>,
+        port: u16,
+    ) -> bool {
+        match self.exit_policy.denial_reason_opt(hostname_opt, port) {
+            Some(reason) => {
+                warning!(
+                    self.logger,
+                    "Refusing to provide exit services for stream {}: exit policy says {}",
+                    stream_key,
+                    reason
+                );
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Near the cap, streams already open may finish, but no new ones start; at the cap, nothing goes
+    fn is_refused_by_data_cap(&self, stream_key: &StreamKey, is_new_stream: bool) -> bool {
+        let reason = match data_cap::status() {
+            DataCapStatus::Open => return false,
+            DataCapStatus::Throttled if !is_new_stream => return false,
+            DataCapStatus::Throttled => "nearly used up",
+            DataCapStatus::Exhausted => "used up",
+        };
+        warning!(
+            self.logger,
+            "Refusing to provide exit services for stream {}: this month's data cap is {}",
+            stream_key,
+            reason
+        );
+        true
+    }
+
+    // A multipath stream's requests arrive over two routes, and its responses go back over both:
+    // the route of the latest request, and the other one before it
+    fn updated_stream_context(
+        &self,
+        stream_key: &StreamKey,
+        return_route: Route,
+        payload_destination_key: PublicKey,
+        paying_wallet: Option<Wallet>,
+    ) -> StreamContext {
+        let (return_route, alternate_return_route_opt) = match self.stream_contexts.get(stream_key)
+        {
+            Some(existing)
+                if !existing.return_route.hops.is_empty()
+                    && existing.return_route.hops.first() != return_route.hops.first() =>
+            {
+                if existing.alternate_return_route_opt.is_none() {
+                    debug!(
+                        self.logger,
+                        "Stream {} has a second route; sending responses over both", stream_key
+                    );
+                }
+                (return_route, Some(existing.return_route.clone()))
+            }
+            Some(existing) => (return_route, existing.alternate_return_route_opt.clone()),
+            None => (return_route, None),
+        };
+        StreamContext {
+            return_route,
+            alternate_return_route_opt,
+            payload_destination_key,
+            paying_wallet,
+        }
+    }
+
+    // Responses go out for the first time here, so this is where the exit service is reported
+    fn send_new_responses(&mut self, stream_key: &StreamKey, packets: Vec<SequencedPacket>) {
+        if let Some(stream_context) = self.stream_contexts.get(stream_key) {
+            packets.into_iter().for_each(|packet| {
+                let data_len = packet.data.len();
+                if self
+                    .send_response_to_hopper(stream_key, packet, stream_context, false)
+                    .is_ok()
+                {
+                    self.report_response_exit_to_accountant(stream_context, data_len);
+                }
+            });
+        }
+        if self
+            .send_windows
+            .get(stream_key)
+            .map(|window| window.is_finished())
+            .unwrap_or(false)
+        {
+            debug!(
+                self.logger,
+                "Retiring stream key {}: all data acknowledged", stream_key
+            );
+            self.retire_stream(stream_key);
+        }
+    }
+
+    fn retire_stream(&mut self, stream_key: &StreamKey) {
+        self.send_windows.remove(stream_key);
+        self.read_gates.remove(stream_key);
+        self.stream_contexts.remove(stream_key);
+        self.served_streams.remove(stream_key);
+        metrics::SERVED_STREAMS.set(self.stream_contexts.len() as u64);
+    }
+
+    // The server is sending faster than the route carries its data away: stop reading from it
+    // until the originating Node has acknowledged enough to catch up
+    fn apply_backpressure(&self, stream_key: &StreamKey) {
+        let buffered_bytes = match self.send_windows.get(stream_key) {
+            Some(window) => window.buffered_bytes(),
+            None => return,
+        };
+        if buffered_bytes >= PAUSE_READING_BYTES {
+            if self.read_gates.pause(stream_key) {
+                debug!(
+                    self.logger,
+                    "Pausing reads on stream {}: {} bytes are waiting to go back",
+                    stream_key,
+                    buffered_bytes
+                );
+            }
+        } else if buffered_bytes <= RESUME_READING_BYTES && self.read_gates.resume(stream_key) {
+            debug!(
+                self.logger,
+                "Resuming reads on stream {}: {} bytes are waiting to go back",
+                stream_key,
+                buffered_bytes
+            );
+        }
+    }
+
+    fn retransmit_overdue_packets(&mut self, now: Instant) {
+        let mut abandoned = vec![];
+        let mut overdue = vec![];
+        for (stream_key, window) in self.send_windows.iter_mut() {
+            match window.retransmission(now) {
+                Retransmission::Due(packets) if packets.is_empty() => (),
+                Retransmission::Due(packets) => overdue.push((*stream_key, packets)),
+                Retransmission::Abandoned => abandoned.push(*stream_key),
+            }
         }
+        overdue.into_iter().for_each(|(stream_key, packets)| {
+            if let Some(stream_context) = self.stream_contexts.get(&stream_key) {
+                debug!(
+                    self.logger,
+                    "Retransmitting {} unacknowledged packet(s) of stream {}",
+                    packets.len(),
+                    stream_key
+                );
+                packets.into_iter().for_each(|packet| {
+                    let _ = self.send_response_to_hopper(&stream_key, packet, stream_context, true);
+                });
+            }
+        });
+        abandoned.into_iter().for_each(|stream_key| {
+            warning!(
+                self.logger,
+                "Abandoning stream {}: a packet went unacknowledged after {} retransmissions",
+                stream_key,
+                MAX_RETRANSMISSIONS
+            );
+            self.retire_stream(&stream_key);
+        });
     }
 
     fn send_response_to_hopper(
         &self,
-        msg: InboundServerData,
+        stream_key: &StreamKey,
+        packet: SequencedPacket,
         stream_context: &StreamContext,
+        is_retransmission: bool,
     ) -> Result<(), ()> {
-        let msg_stream_key = msg.stream_key;
-        let msg_data_len = msg.data.len() as u32;
-        let msg_source = msg.source;
-        let msg_sequence_number = msg.sequence_number;
+        let data_len = packet.data.len();
+        let sequence_number = packet.sequence_number;
         let payload = MessageType::ClientResponse(VersionedData::new(
             &crate::sub_lib::migrations::client_response_payload::MIGRATIONS,
             &ClientResponsePayload_0v1 {
-                stream_key: msg.stream_key,
-                sequenced_packet: SequencedPacket {
-                    data: msg.data,
-                    sequence_number: msg.sequence_number,
-                    last_data: msg.last_data,
-                },
+                stream_key: *stream_key,
+                sequenced_packet: packet,
             },
         ));
         debug!(
             self.logger,
             "Sending ClientResponsePayload to Hopper: stream {}, sequence {}, length {}",
-            msg_stream_key,
-            msg_sequence_number,
-            msg_data_len
+            stream_key,
+            sequence_number,
+            data_len
         );
         let icp = match IncipientCoresPackage::new(
             self.cryptde,
-            stream_context.return_route.clone(),
+            stream_context
+                .return_route_for(sequence_number, is_retransmission)
+                .clone(),
             payload,
             &stream_context.payload_destination_key,
         ) {
             Ok(icp) => icp,
             Err(err) => {
-                error!(self.logger, "Could not create CORES package for {}-byte response from {}, seq {}: {} - ignoring", msg_data_len, msg_source, msg_sequence_number, err);
+                error!(self.logger, "Could not create CORES package for {}-byte response on stream {}, seq {}: {} - ignoring", data_len, stream_key, sequence_number, err);
                 return Err(());
             }
         };
@@ -312,23 +883,38 @@ impl ProxyClient {
     }
 }
 
+#[derive(PartialEq, Eq)]
 struct StreamContext {
     return_route: Route,
+    alternate_return_route_opt: Option<Route>,
     payload_destination_key: PublicKey,
     paying_wallet: Option<Wallet>,
 }
 
+impl StreamContext {
+    // With two routes, responses alternate between them, and retransmissions take the other
+    // route than the original did, in case that route is where it got lost
+    fn return_route_for(&self, sequence_number: u64, is_retransmission: bool) -> &Route {
+        match &self.alternate_return_route_opt {
+            Some(alternate) if (sequence_number % 2 == 1) != is_retransmission => alternate,
+            _ => &self.return_route,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::blockchain::blockchain_interface::ROPSTEN_TESTNET_CONTRACT_ADDRESS;
     use crate::proxy_client::local_test_utils::ResolverWrapperFactoryMock;
     use crate::proxy_client::local_test_utils::ResolverWrapperMock;
+    use crate::proxy_client::protocol_pack::PassthroughExitProtocolPack;
     use crate::proxy_client::resolver_wrapper::ResolverWrapper;
     use crate::proxy_client::stream_handler_pool::StreamHandlerPool;
     use crate::proxy_client::stream_handler_pool::StreamHandlerPoolFactory;
     use crate::sub_lib::accountant::ReportExitServiceProvidedMessage;
     use crate::sub_lib::cryptde::CryptData;
+    use crate::sub_lib::cryptde::PlainData;
     use crate::sub_lib::cryptde::PublicKey;
     use crate::sub_lib::dispatcher::Component;
     use crate::sub_lib::hopper::MessageType;
@@ -337,6 +923,7 @@ mod tests {
     use crate::sub_lib::proxy_server::ProxyProtocol;
     use crate::sub_lib::route::{Route, RouteSegment};
     use crate::sub_lib::sequence_buffer::SequencedPacket;
+    use crate::sub_lib::sequence_window::RETRANSMIT_TIMEOUT;
     use crate::sub_lib::versioned_data::VersionedData;
     use crate::sub_lib::wallet::Wallet;
     use crate::test_utils::logging::init_test_logging;
@@ -346,6 +933,7 @@ mod tests {
     use crate::test_utils::recorder::Recorder;
     use crate::test_utils::*;
     use actix::System;
+    use futures::Future;
     use std::cell::RefCell;
     use std::net::IpAddr;
     use std::net::SocketAddr;
@@ -358,8 +946,11 @@ mod tests {
         vec![SocketAddr::from_str("8.8.8.8:53").unwrap()]
     }
 
+    type UdpPackageParameters = Arc<Mutex<Vec<(ClientUdpRequestPayload_0v1, Option<Wallet>)>>>;
+
     pub struct StreamHandlerPoolMock {
         process_package_parameters: Arc<Mutex<Vec<(ClientRequestPayload_0v1, Option<Wallet>)>>>,
+        process_udp_package_parameters: UdpPackageParameters,
     }
 
     impl StreamHandlerPool for StreamHandlerPoolMock {
@@ -373,14 +964,34 @@ mod tests {
                 .unwrap()
                 .push((payload, paying_wallet));
         }
-    }
 
-    impl StreamHandlerPoolMock {
-        pub fn new() -> StreamHandlerPoolMock {
-            StreamHandlerPoolMock {
-                process_package_parameters: Arc::new(Mutex::new(vec![])),
-            }
-        }
+        fn process_udp_package(
+            &self,
+            payload: ClientUdpRequestPayload_0v1,
+            paying_wallet: Option<Wallet>,
+        ) {
+            self.process_udp_package_parameters
+                .lock()
+                .unwrap()
+                .push((payload, paying_wallet));
+        }
+    }
+
+    impl StreamHandlerPoolMock {
+        pub fn new() -> StreamHandlerPoolMock {
+            StreamHandlerPoolMock {
+                process_package_parameters: Arc::new(Mutex::new(vec![])),
+                process_udp_package_parameters: Arc::new(Mutex::new(vec![])),
+            }
+        }
+
+        pub fn process_udp_package_parameters(
+            self,
+            parameters: &mut UdpPackageParameters,
+        ) -> StreamHandlerPoolMock {
+            *parameters = self.process_udp_package_parameters.clone();
+            self
+        }
 
         pub fn process_package_parameters(
             self,
@@ -401,6 +1012,9 @@ mod tests {
                     ProxyClientSubs,
                     u64,
                     u64,
+                    Option<Duration>,
+                    Arc<ProtocolPackRegistry>,
+                    ReadGates,
                 )>,
             >,
         >,
@@ -416,6 +1030,9 @@ mod tests {
             proxy_client_subs: ProxyClientSubs,
             exit_service_rate: u64,
             exit_byte_rate: u64,
+            stream_idle_timeout_opt: Option<Duration>,
+            protocol_packs: Arc<ProtocolPackRegistry>,
+            read_gates: ReadGates,
         ) -> Box<dyn StreamHandlerPool> {
             self.make_parameters.lock().unwrap().push((
                 resolver,
@@ -424,6 +1041,9 @@ mod tests {
                 proxy_client_subs,
                 exit_service_rate,
                 exit_byte_rate,
+                stream_idle_timeout_opt,
+                protocol_packs,
+                read_gates,
             ));
             self.make_results.borrow_mut().remove(0)
         }
@@ -448,6 +1068,9 @@ mod tests {
                         ProxyClientSubs,
                         u64,
                         u64,
+                        Option<Duration>,
+                        Arc<ProtocolPackRegistry>,
+                        ReadGates,
                     )>,
                 >,
             >,
@@ -473,6 +1096,11 @@ mod tests {
         ProxyClient::new(ProxyClientConfig {
             cryptde: main_cryptde(),
             dns_servers: vec![],
+            secure_dns_servers: vec![],
+            dns_cache_size: 0,
+            dns_lookup_threads: 1,
+            exit_policy: ExitPolicy::default(),
+            stream_idle_timeout_opt: None,
             exit_service_rate: 100,
             exit_byte_rate: 200,
         });
@@ -500,6 +1128,11 @@ mod tests {
                 SocketAddr::from_str("4.3.2.1:4321").unwrap(),
                 SocketAddr::from_str("5.4.3.2:5432").unwrap(),
             ],
+            secure_dns_servers: vec![],
+            dns_cache_size: 0,
+            dns_lookup_threads: 1,
+            exit_policy: ExitPolicy::default(),
+            stream_idle_timeout_opt: Some(Duration::from_secs(300)),
             exit_service_rate: 100,
             exit_byte_rate: 200,
         });
@@ -534,6 +1167,155 @@ mod tests {
         );
         assert_eq!(opts, ResolverOpts::default());
         assert_eq!(resolver_wrapper_new_parameters.is_empty(), true);
+        let pool_factory_make_parameters = pool_factory_make_parameters.lock().unwrap();
+        let (_, _, _, _, exit_service_rate, exit_byte_rate, stream_idle_timeout_opt, _, _) =
+            &pool_factory_make_parameters[0];
+        assert_eq!(*exit_service_rate, 100);
+        assert_eq!(*exit_byte_rate, 200);
+        assert_eq!(*stream_idle_timeout_opt, Some(Duration::from_secs(300)));
+    }
+
+    #[test]
+    fn bind_puts_a_dns_cache_in_front_of_the_resolver_when_it_has_a_size() {
+        let system =
+            System::new("bind_puts_a_dns_cache_in_front_of_the_resolver_when_it_has_a_size");
+        let lookup_ip_parameters_arc = Arc::new(Mutex::new(vec![]));
+        let resolver_wrapper = ResolverWrapperMock::new()
+            .lookup_ip_parameters(&lookup_ip_parameters_arc)
+            .lookup_ip_success(vec![IpAddr::from_str("1.2.3.4").unwrap()]);
+        let resolver_wrapper_factory =
+            ResolverWrapperFactoryMock::new().new_result(Box::new(resolver_wrapper));
+        let mut pool_factory_make_parameters = Arc::new(Mutex::new(vec![]));
+        let pool_factory = StreamHandlerPoolFactoryMock::new()
+            .make_parameters(&mut pool_factory_make_parameters)
+            .make_result(Box::new(StreamHandlerPoolMock::new()));
+        let peer_actors = peer_actors_builder().build();
+        let mut subject = ProxyClient::new(ProxyClientConfig {
+            cryptde: main_cryptde(),
+            dns_servers: vec![SocketAddr::from_str("4.3.2.1:4321").unwrap()],
+            secure_dns_servers: vec![],
+            dns_cache_size: 10,
+            dns_lookup_threads: 1,
+            exit_policy: ExitPolicy::default(),
+            stream_idle_timeout_opt: None,
+            exit_service_rate: 100,
+            exit_byte_rate: 200,
+        });
+        subject.resolver_wrapper_factory = Box::new(resolver_wrapper_factory);
+        subject.stream_handler_pool_factory = Box::new(pool_factory);
+        let subject_addr: Addr<ProxyClient> = subject.start();
+
+        subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+
+        System::current().stop_with_code(0);
+        system.run();
+        let pool_factory_make_parameters = pool_factory_make_parameters.lock().unwrap();
+        let resolver = &pool_factory_make_parameters[0].0;
+        let first = resolver.lookup_ip("example.com").wait().unwrap();
+        let second = resolver.lookup_ip("example.com").wait().unwrap();
+        assert_eq!(
+            first.iter().collect::<Vec<IpAddr>>(),
+            second.iter().collect::<Vec<IpAddr>>()
+        );
+        assert_eq!(
+            *lookup_ip_parameters_arc.lock().unwrap(),
+            vec!["example.com".to_string()]
+        );
+    }
+
+    #[test]
+    fn registered_protocol_pack_reaches_stream_handler_pool() {
+        let system = System::new("registered_protocol_pack_reaches_stream_handler_pool");
+        let resolver_wrapper_factory =
+            ResolverWrapperFactoryMock::new().new_result(Box::new(ResolverWrapperMock::new()));
+        let mut pool_factory_make_parameters = Arc::new(Mutex::new(vec![]));
+        let pool_factory = StreamHandlerPoolFactoryMock::new()
+            .make_parameters(&mut pool_factory_make_parameters)
+            .make_result(Box::new(StreamHandlerPoolMock::new()));
+        let peer_actors = peer_actors_builder().build();
+        let mut subject = ProxyClient::new(ProxyClientConfig {
+            cryptde: main_cryptde(),
+            dns_servers: vec![SocketAddr::from_str("4.3.2.1:4321").unwrap()],
+            secure_dns_servers: vec![],
+            dns_cache_size: 0,
+            dns_lookup_threads: 1,
+            exit_policy: ExitPolicy::default(),
+            stream_idle_timeout_opt: None,
+            exit_service_rate: 100,
+            exit_byte_rate: 200,
+        });
+        subject.resolver_wrapper_factory = Box::new(resolver_wrapper_factory);
+        subject.stream_handler_pool_factory = Box::new(pool_factory);
+        subject.register_protocol_pack(Box::new(PassthroughExitProtocolPack {
+            proxy_protocol: ProxyProtocol::SMTP,
+        }));
+        let subject_addr: Addr<ProxyClient> = subject.start();
+
+        subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+
+        System::current().stop_with_code(0);
+        system.run();
+        let pool_factory_make_parameters = pool_factory_make_parameters.lock().unwrap();
+        let protocol_packs = &pool_factory_make_parameters[0].7;
+        assert_eq!(
+            protocol_packs
+                .pack_for(ProxyProtocol::SMTP)
+                .server_speaks_first(),
+            false
+        );
+        assert_eq!(
+            protocol_packs.pack_for(ProxyProtocol::TLS).proxy_protocol(),
+            ProxyProtocol::TLS
+        );
+    }
+
+    #[test]
+    fn bind_uses_secure_resolver_when_secure_dns_servers_are_configured() {
+        init_test_logging();
+        let system =
+            System::new("bind_uses_secure_resolver_when_secure_dns_servers_are_configured");
+        let mut make_parameters_arc: Arc<Mutex<Vec<(ResolverConfig, ResolverOpts)>>> =
+            Arc::new(Mutex::new(vec![]));
+        let make_secure_parameters_arc = Arc::new(Mutex::new(vec![]));
+        let resolver_wrapper_factory = ResolverWrapperFactoryMock::new()
+            .new_parameters(&mut make_parameters_arc)
+            .make_secure_parameters(&make_secure_parameters_arc)
+            .new_result(Box::new(ResolverWrapperMock::new()));
+        let pool_factory =
+            StreamHandlerPoolFactoryMock::new().make_result(Box::new(StreamHandlerPoolMock::new()));
+        let peer_actors = peer_actors_builder().build();
+        let secure_dns_servers = vec![
+            SecureDnsServer::from_str("https://cloudflare-dns.com/dns-query").unwrap(),
+            SecureDnsServer::from_str("tls://9.9.9.9").unwrap(),
+        ];
+        let mut subject = ProxyClient::new(ProxyClientConfig {
+            cryptde: main_cryptde(),
+            dns_servers: vec![SocketAddr::from_str("4.3.2.1:53").unwrap()],
+            secure_dns_servers: secure_dns_servers.clone(),
+            dns_cache_size: 0,
+            dns_lookup_threads: 7,
+            exit_policy: ExitPolicy::default(),
+            stream_idle_timeout_opt: None,
+            exit_service_rate: 100,
+            exit_byte_rate: 200,
+        });
+        subject.resolver_wrapper_factory = Box::new(resolver_wrapper_factory);
+        subject.stream_handler_pool_factory = Box::new(pool_factory);
+        let subject_addr: Addr<ProxyClient> = subject.start();
+
+        subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+
+        System::current().stop_with_code(0);
+        system.run();
+
+        assert_eq!(
+            *make_secure_parameters_arc.lock().unwrap(),
+            vec![(secure_dns_servers, 7)]
+        );
+        assert_eq!(make_parameters_arc.lock().unwrap().is_empty(), true);
+        TestLogHandler::new().exists_log_containing(
+            "INFO: ProxyClient: Adding secure DNS server: tls://9.9.9.9:853",
+        );
     }
 
     #[test]
@@ -550,9 +1332,10 @@ mod tests {
             target_port: 1234,
             protocol: ProxyProtocol::HTTP,
             originator_public_key: PublicKey::new(&b"originator_public_key"[..]),
+            is_resumption: false,
         };
         let cryptde = main_cryptde();
-        let package = ExpiredCoresPackage::new(
+        let package: ExpiredCoresPackage<ClientRequestPayload_0v1> = ExpiredCoresPackage::new(
             SocketAddr::from_str("1.2.3.4:1234").unwrap(),
             Some(make_wallet("consuming")),
             route_to_proxy_client(&cryptde.public_key(), cryptde),
@@ -563,6 +1346,11 @@ mod tests {
         let subject = ProxyClient::new(ProxyClientConfig {
             cryptde,
             dns_servers: dnss(),
+            secure_dns_servers: vec![],
+            dns_cache_size: 0,
+            dns_lookup_threads: 1,
+            exit_policy: ExitPolicy::default(),
+            stream_idle_timeout_opt: None,
             exit_service_rate: 100,
             exit_byte_rate: 200,
         });
@@ -585,6 +1373,11 @@ mod tests {
             let subject = ProxyClient::new(ProxyClientConfig {
                 cryptde,
                 dns_servers: vec![SocketAddr::from_str("1.1.1.1:53").unwrap()],
+                secure_dns_servers: vec![],
+                dns_cache_size: 0,
+                dns_lookup_threads: 1,
+                exit_policy: ExitPolicy::default(),
+                stream_idle_timeout_opt: None,
                 exit_service_rate: 0,
                 exit_byte_rate: 0,
             });
@@ -624,6 +1417,11 @@ mod tests {
             let mut subject = ProxyClient::new(ProxyClientConfig {
                 cryptde,
                 dns_servers: vec![SocketAddr::from_str("1.1.1.1:53").unwrap()],
+                secure_dns_servers: vec![],
+                dns_cache_size: 0,
+                dns_lookup_threads: 1,
+                exit_policy: ExitPolicy::default(),
+                stream_idle_timeout_opt: None,
                 exit_service_rate: 0,
                 exit_byte_rate: 0,
             });
@@ -631,6 +1429,7 @@ mod tests {
                 stream_key_inner,
                 StreamContext {
                     return_route: return_route_inner,
+                    alternate_return_route_opt: None,
                     payload_destination_key: originator_key_inner,
                     paying_wallet: None,
                 },
@@ -673,6 +1472,99 @@ mod tests {
         );
     }
 
+    #[test]
+    fn payment_overdue_notices_go_back_over_a_stream_the_debtor_is_paying_for() {
+        init_test_logging();
+        let cryptde = main_cryptde();
+        let (hopper, hopper_awaiter, hopper_recording_arc) = make_recorder();
+        let debtor = make_wallet("debtor");
+        let return_route = make_meaningless_route();
+        let originator_key = PublicKey::new(&b"debtor's key"[..]);
+        let notice = PaymentOverdue_0v1 {
+            creditor: make_wallet("creditor"),
+            debtor: debtor.clone(),
+            balance: 1_234_567_890,
+            age_sec: 200_000,
+            ban_in_sec: 21_600,
+        };
+        let return_route_inner = return_route.clone();
+        let originator_key_inner = originator_key.clone();
+        let notice_inner = notice.clone();
+        thread::spawn(move || {
+            let system = System::new(
+                "payment_overdue_notices_go_back_over_a_stream_the_debtor_is_paying_for",
+            );
+            let peer_actors = peer_actors_builder().hopper(hopper).build();
+            let mut subject = ProxyClient::new(ProxyClientConfig {
+                cryptde,
+                dns_servers: vec![SocketAddr::from_str("1.1.1.1:53").unwrap()],
+                secure_dns_servers: vec![],
+                dns_cache_size: 0,
+                dns_lookup_threads: 1,
+                exit_policy: ExitPolicy::default(),
+                stream_idle_timeout_opt: None,
+                exit_service_rate: 0,
+                exit_byte_rate: 0,
+            });
+            subject.stream_contexts.insert(
+                StreamKey::new(
+                    PublicKey::new(&b"someone else"[..]),
+                    SocketAddr::from_str("2.3.4.5:6789").unwrap(),
+                ),
+                StreamContext {
+                    return_route: Route { hops: vec![] },
+                    alternate_return_route_opt: None,
+                    payload_destination_key: PublicKey::new(&b"someone else"[..]),
+                    paying_wallet: Some(make_wallet("prompt payer")),
+                },
+            );
+            subject.stream_contexts.insert(
+                make_meaningless_stream_key(),
+                StreamContext {
+                    return_route: return_route_inner,
+                    alternate_return_route_opt: None,
+                    payload_destination_key: originator_key_inner,
+                    paying_wallet: Some(debtor),
+                },
+            );
+            let subject_addr = subject.start();
+            let subject_subs = ProxyClient::make_subs_from(&subject_addr);
+            send_bind_message!(subject_subs, peer_actors);
+            let stranger_notice = PaymentOverdue_0v1 {
+                debtor: make_wallet("stranger"),
+                ..notice_inner.clone()
+            };
+
+            subject_subs
+                .payment_overdue
+                .try_send(stranger_notice)
+                .unwrap();
+            subject_subs.payment_overdue.try_send(notice_inner).unwrap();
+
+            system.run();
+        });
+
+        hopper_awaiter.await_message_count(1);
+        assert_eq!(
+            hopper_recording_arc
+                .lock()
+                .unwrap()
+                .get_record::<IncipientCoresPackage>(0),
+            &IncipientCoresPackage::new(
+                cryptde,
+                return_route,
+                MessageType::PaymentOverdue(notice.into()),
+                &originator_key
+            )
+            .unwrap()
+        );
+        TestLogHandler::new().await_log_containing(
+            "DEBUG: ProxyClient: No open stream to send a payment-overdue notice for",
+            1000,
+        );
+        assert_eq!(hopper_recording_arc.lock().unwrap().len(), 1);
+    }
+
     #[test]
     fn data_from_hopper_is_relayed_to_stream_handler_pool() {
         let cryptde = main_cryptde();
@@ -687,8 +1579,9 @@ mod tests {
             target_port: 0,
             protocol: ProxyProtocol::HTTP,
             originator_public_key: PublicKey::new(&b"originator"[..]),
+            is_resumption: false,
         };
-        let package = ExpiredCoresPackage::new(
+        let package: ExpiredCoresPackage<ClientRequestPayload_0v1> = ExpiredCoresPackage::new(
             SocketAddr::from_str("1.2.3.4:1234").unwrap(),
             Some(make_wallet("consuming")),
             make_meaningless_route(),
@@ -711,6 +1604,11 @@ mod tests {
         let mut subject = ProxyClient::new(ProxyClientConfig {
             cryptde,
             dns_servers: dnss(),
+            secure_dns_servers: vec![],
+            dns_cache_size: 0,
+            dns_lookup_threads: 1,
+            exit_policy: ExitPolicy::default(),
+            stream_idle_timeout_opt: None,
             exit_service_rate: 100,
             exit_byte_rate: 200,
         });
@@ -742,8 +1640,9 @@ mod tests {
             target_port: 0,
             protocol: ProxyProtocol::HTTP,
             originator_public_key: PublicKey::new(&b"originator"[..]),
+            is_resumption: false,
         };
-        let package = ExpiredCoresPackage::new(
+        let package: ExpiredCoresPackage<ClientRequestPayload_0v1> = ExpiredCoresPackage::new(
             SocketAddr::from_str("1.2.3.4:1234").unwrap(),
             None,
             make_meaningless_route(),
@@ -766,6 +1665,11 @@ mod tests {
         let mut subject = ProxyClient::new(ProxyClientConfig {
             cryptde,
             dns_servers: dnss(),
+            secure_dns_servers: vec![],
+            dns_cache_size: 0,
+            dns_lookup_threads: 1,
+            exit_policy: ExitPolicy::default(),
+            stream_idle_timeout_opt: None,
             exit_service_rate: rate_pack_exit(100),
             exit_byte_rate: rate_pack_exit_byte(100),
         });
@@ -797,6 +1701,7 @@ mod tests {
             target_port: 0,
             protocol: ProxyProtocol::HTTP,
             originator_public_key: alias_cryptde.public_key().clone(),
+            is_resumption: false,
         };
         let zero_hop_remaining_route = Route::one_way(
             RouteSegment::new(
@@ -808,7 +1713,7 @@ mod tests {
             Some(ROPSTEN_TESTNET_CONTRACT_ADDRESS),
         )
         .unwrap();
-        let package = ExpiredCoresPackage::new(
+        let package: ExpiredCoresPackage<ClientRequestPayload_0v1> = ExpiredCoresPackage::new(
             SocketAddr::from_str("1.2.3.4:1234").unwrap(),
             None,
             zero_hop_remaining_route,
@@ -831,6 +1736,11 @@ mod tests {
         let mut subject = ProxyClient::new(ProxyClientConfig {
             cryptde: main_cryptde,
             dns_servers: dnss(),
+            secure_dns_servers: vec![],
+            dns_cache_size: 0,
+            dns_lookup_threads: 1,
+            exit_policy: ExitPolicy::default(),
+            stream_idle_timeout_opt: None,
             exit_service_rate: rate_pack_exit(100),
             exit_byte_rate: rate_pack_exit_byte(100),
         });
@@ -848,83 +1758,489 @@ mod tests {
     }
 
     #[test]
-    fn inbound_server_data_is_translated_to_cores_packages() {
-        init_test_logging();
-        let (hopper, _, hopper_recording_arc) = make_recorder();
-        let (accountant, _, accountant_recording_arc) = make_recorder();
-        let stream_key = make_meaningless_stream_key();
-        let data: &[u8] = b"An honest politician is one who, when he is bought, will stay bought.";
-        let system = System::new("inbound_server_data_is_translated_to_cores_packages");
+    fn udp_data_from_hopper_is_relayed_to_stream_handler_pool() {
+        let cryptde = main_cryptde();
+        let request = ClientUdpRequestPayload_0v1 {
+            stream_key: make_meaningless_stream_key(),
+            target_hostname: "realdomain.nu".to_string(),
+            target_port: 443,
+            data: PlainData::new(b"quic hello"),
+            originator_public_key: PublicKey::new(&b"originator"[..]),
+        };
+        let package: ExpiredCoresPackage<ClientUdpRequestPayload_0v1> = ExpiredCoresPackage::new(
+            SocketAddr::from_str("1.2.3.4:1234").unwrap(),
+            Some(make_wallet("consuming")),
+            make_meaningless_route(),
+            request.clone(),
+            0,
+        );
+        let system = System::new("udp_data_from_hopper_is_relayed_to_stream_handler_pool");
+        let peer_actors = peer_actors_builder().build();
+        let mut process_udp_package_parameters = Arc::new(Mutex::new(vec![]));
+        let pool = Box::new(
+            StreamHandlerPoolMock::new()
+                .process_udp_package_parameters(&mut process_udp_package_parameters),
+        );
+        let pool_factory = StreamHandlerPoolFactoryMock::new().make_result(pool);
         let mut subject = ProxyClient::new(ProxyClientConfig {
-            cryptde: main_cryptde(),
-            dns_servers: vec![SocketAddr::from_str("8.7.6.5:4321").unwrap()],
+            cryptde,
+            dns_servers: dnss(),
+            secure_dns_servers: vec![],
+            dns_cache_size: 0,
+            dns_lookup_threads: 1,
+            exit_policy: ExitPolicy::default(),
+            stream_idle_timeout_opt: None,
             exit_service_rate: 100,
             exit_byte_rate: 200,
         });
-        subject.stream_contexts.insert(
-            stream_key.clone(),
-            StreamContext {
-                return_route: make_meaningless_route(),
-                payload_destination_key: PublicKey::new(&b"abcd"[..]),
-                paying_wallet: Some(make_wallet("paying")),
+        subject.stream_handler_pool_factory = Box::new(pool_factory);
+        let subject_addr: Addr<ProxyClient> = subject.start();
+        subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+
+        subject_addr.try_send(package).unwrap();
+
+        System::current().stop_with_code(0);
+        system.run();
+        let parameter = process_udp_package_parameters.lock().unwrap().remove(0);
+        assert_eq!(parameter, (request, Some(make_wallet("consuming"))));
+    }
+
+    #[test]
+    fn refuse_to_relay_udp_with_no_paying_wallet() {
+        init_test_logging();
+        let package: ExpiredCoresPackage<ClientUdpRequestPayload_0v1> = ExpiredCoresPackage::new(
+            SocketAddr::from_str("1.2.3.4:1234").unwrap(),
+            None,
+            make_meaningless_route(),
+            ClientUdpRequestPayload_0v1 {
+                stream_key: make_meaningless_stream_key(),
+                target_hostname: "realdomain.nu".to_string(),
+                target_port: 443,
+                data: PlainData::new(b"quic hello"),
+                originator_public_key: PublicKey::new(&b"originator"[..]),
             },
+            0,
+        );
+        let system = System::new("refuse_to_relay_udp_with_no_paying_wallet");
+        let peer_actors = peer_actors_builder().build();
+        let mut process_udp_package_parameters = Arc::new(Mutex::new(vec![]));
+        let pool = Box::new(
+            StreamHandlerPoolMock::new()
+                .process_udp_package_parameters(&mut process_udp_package_parameters),
         );
+        let pool_factory = StreamHandlerPoolFactoryMock::new().make_result(pool);
+        let mut subject = ProxyClient::new(ProxyClientConfig {
+            cryptde: main_cryptde(),
+            dns_servers: dnss(),
+            secure_dns_servers: vec![],
+            dns_cache_size: 0,
+            dns_lookup_threads: 1,
+            exit_policy: ExitPolicy::default(),
+            stream_idle_timeout_opt: None,
+            exit_service_rate: 100,
+            exit_byte_rate: 200,
+        });
+        subject.stream_handler_pool_factory = Box::new(pool_factory);
         let subject_addr: Addr<ProxyClient> = subject.start();
-        let peer_actors = peer_actors_builder()
-            .hopper(hopper)
-            .accountant(accountant)
-            .build();
-
         subject_addr.try_send(BindMessage { peer_actors }).unwrap();
 
-        subject_addr
-            .try_send(InboundServerData {
-                stream_key: stream_key.clone(),
-                last_data: false,
-                sequence_number: 1234,
-                source: SocketAddr::from_str("1.2.3.4:5678").unwrap(),
-                data: Vec::from(data),
-            })
-            .unwrap();
-        subject_addr
-            .try_send(InboundServerData {
-                stream_key: stream_key.clone(),
-                last_data: true,
-                sequence_number: 1235,
-                source: SocketAddr::from_str("1.2.3.4:5678").unwrap(),
-                data: Vec::from(data),
-            })
-            .unwrap();
-        subject_addr
-            .try_send(InboundServerData {
-                stream_key: stream_key.clone(),
-                last_data: false,
-                sequence_number: 1236,
-                source: SocketAddr::from_str("1.2.3.4:5678").unwrap(),
-                data: Vec::from(data),
-            })
-            .unwrap();
-        subject_addr
-            .try_send(InboundServerData {
-                stream_key: stream_key.clone(),
-                last_data: true,
-                sequence_number: 1237,
-                source: SocketAddr::from_str("1.2.3.4:5678").unwrap(),
-                data: Vec::from(data),
-            })
-            .unwrap();
+        subject_addr.try_send(package).unwrap();
 
         System::current().stop_with_code(0);
         system.run();
-        let hopper_recording = hopper_recording_arc.lock().unwrap();
-        assert_eq!(
-            hopper_recording.get_record::<IncipientCoresPackage>(0),
-            &IncipientCoresPackage::new(
-                main_cryptde(),
-                make_meaningless_route(),
-                MessageType::ClientResponse(VersionedData::new(
-                    &crate::sub_lib::migrations::client_response_payload::MIGRATIONS,
-                    &ClientResponsePayload_0v1 {
+        assert_eq!(process_udp_package_parameters.lock().unwrap().len(), 0);
+        TestLogHandler::new().exists_log_containing("WARN: ProxyClient: Refusing to provide exit services for CORES package with 10-byte UDP payload without paying wallet");
+    }
+
+    #[test]
+    fn refuse_to_provide_exit_services_denied_by_exit_policy() {
+        init_test_logging();
+        let stream_key = make_meaningless_stream_key();
+        let request = ClientRequestPayload_0v1 {
+            stream_key,
+            sequenced_packet: SequencedPacket {
+                data: b"inbound data".to_vec(),
+                sequence_number: 0,
+                last_data: false,
+            },
+            target_hostname: Some("www.example.com".to_string()),
+            target_port: 443,
+            protocol: ProxyProtocol::TLS,
+            originator_public_key: PublicKey::new(&b"originator"[..]),
+            is_resumption: false,
+        };
+        let package: ExpiredCoresPackage<ClientRequestPayload_0v1> = ExpiredCoresPackage::new(
+            SocketAddr::from_str("1.2.3.4:1234").unwrap(),
+            Some(make_wallet("consuming")),
+            make_meaningless_route(),
+            request,
+            0,
+        );
+        let system = System::new("refuse_to_provide_exit_services_denied_by_exit_policy");
+        let peer_actors = peer_actors_builder().build();
+        let mut process_package_parameters = Arc::new(Mutex::new(vec![]));
+        let pool = Box::new(
+            StreamHandlerPoolMock::new()
+                .process_package_parameters(&mut process_package_parameters),
+        );
+        let pool_factory = StreamHandlerPoolFactoryMock::new().make_result(pool);
+        let mut subject = ProxyClient::new(ProxyClientConfig {
+            cryptde: main_cryptde(),
+            dns_servers: dnss(),
+            secure_dns_servers: vec![],
+            dns_cache_size: 0,
+            dns_lookup_threads: 1,
+            exit_policy: ExitPolicy::from_str("host:example.com").unwrap(),
+            stream_idle_timeout_opt: None,
+            exit_service_rate: 100,
+            exit_byte_rate: 200,
+        });
+        subject.stream_handler_pool_factory = Box::new(pool_factory);
+        let subject_addr: Addr<ProxyClient> = subject.start();
+        subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+
+        subject_addr.try_send(package).unwrap();
+
+        System::current().stop_with_code(0);
+        system.run();
+        assert_eq!(process_package_parameters.lock().unwrap().len(), 0);
+        TestLogHandler::new().exists_log_containing(&format!(
+            "WARN: ProxyClient: Refusing to provide exit services for stream {}: exit policy says host www.example.com is denied by rule example.com",
+            stream_key
+        ));
+    }
+
+    #[test]
+    fn refuse_to_start_new_exit_streams_when_data_cap_is_nearly_used_up() {
+        init_test_logging();
+        let stream_key = make_meaningless_stream_key();
+        let request = ClientRequestPayload_0v1 {
+            stream_key,
+            sequenced_packet: SequencedPacket {
+                data: b"inbound data".to_vec(),
+                sequence_number: 0,
+                last_data: false,
+            },
+            target_hostname: Some("www.example.com".to_string()),
+            target_port: 443,
+            protocol: ProxyProtocol::TLS,
+            originator_public_key: PublicKey::new(&b"originator"[..]),
+            is_resumption: false,
+        };
+        let package: ExpiredCoresPackage<ClientRequestPayload_0v1> = ExpiredCoresPackage::new(
+            SocketAddr::from_str("1.2.3.4:1234").unwrap(),
+            Some(make_wallet("consuming")),
+            make_meaningless_route(),
+            request,
+            0,
+        );
+        let system =
+            System::new("refuse_to_start_new_exit_streams_when_data_cap_is_nearly_used_up");
+        let peer_actors = peer_actors_builder().build();
+        let mut process_package_parameters = Arc::new(Mutex::new(vec![]));
+        let pool = Box::new(
+            StreamHandlerPoolMock::new()
+                .process_package_parameters(&mut process_package_parameters),
+        );
+        let pool_factory = StreamHandlerPoolFactoryMock::new().make_result(pool);
+        let mut subject = ProxyClient::new(ProxyClientConfig {
+            cryptde: main_cryptde(),
+            dns_servers: dnss(),
+            secure_dns_servers: vec![],
+            dns_cache_size: 0,
+            dns_lookup_threads: 1,
+            exit_policy: ExitPolicy::default(),
+            stream_idle_timeout_opt: None,
+            exit_service_rate: 100,
+            exit_byte_rate: 200,
+        });
+        subject.stream_handler_pool_factory = Box::new(pool_factory);
+        data_cap::set_status(DataCapStatus::Throttled);
+        let subject_addr: Addr<ProxyClient> = subject.start();
+        subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+
+        subject_addr.try_send(package).unwrap();
+
+        System::current().stop_with_code(0);
+        system.run();
+        data_cap::set_status(DataCapStatus::Open);
+        assert_eq!(process_package_parameters.lock().unwrap().len(), 0);
+        TestLogHandler::new().exists_log_containing(&format!(
+            "WARN: ProxyClient: Refusing to provide exit services for stream {}: this month's data cap is nearly used up",
+            stream_key
+        ));
+    }
+
+    #[test]
+    fn keep_serving_open_exit_streams_when_data_cap_is_nearly_used_up() {
+        let stream_key = make_meaningless_stream_key();
+        let request = ClientRequestPayload_0v1 {
+            stream_key,
+            sequenced_packet: SequencedPacket {
+                data: b"inbound data".to_vec(),
+                sequence_number: 0,
+                last_data: false,
+            },
+            target_hostname: Some("www.example.com".to_string()),
+            target_port: 443,
+            protocol: ProxyProtocol::TLS,
+            originator_public_key: PublicKey::new(&b"originator"[..]),
+            is_resumption: false,
+        };
+        let package: ExpiredCoresPackage<ClientRequestPayload_0v1> = ExpiredCoresPackage::new(
+            SocketAddr::from_str("1.2.3.4:1234").unwrap(),
+            Some(make_wallet("consuming")),
+            make_meaningless_route(),
+            request,
+            0,
+        );
+        let system = System::new("keep_serving_open_exit_streams_when_data_cap_is_nearly_used_up");
+        let peer_actors = peer_actors_builder().build();
+        let mut process_package_parameters = Arc::new(Mutex::new(vec![]));
+        let pool = Box::new(
+            StreamHandlerPoolMock::new()
+                .process_package_parameters(&mut process_package_parameters),
+        );
+        let pool_factory = StreamHandlerPoolFactoryMock::new().make_result(pool);
+        let mut subject = ProxyClient::new(ProxyClientConfig {
+            cryptde: main_cryptde(),
+            dns_servers: dnss(),
+            secure_dns_servers: vec![],
+            dns_cache_size: 0,
+            dns_lookup_threads: 1,
+            exit_policy: ExitPolicy::default(),
+            stream_idle_timeout_opt: None,
+            exit_service_rate: 100,
+            exit_byte_rate: 200,
+        });
+        subject.stream_handler_pool_factory = Box::new(pool_factory);
+        subject.stream_contexts.insert(
+            stream_key,
+            StreamContext {
+                return_route: make_meaningless_route(),
+                alternate_return_route_opt: None,
+                payload_destination_key: PublicKey::new(&b"originator"[..]),
+                paying_wallet: Some(make_wallet("consuming")),
+            },
+        );
+        data_cap::set_status(DataCapStatus::Throttled);
+        let subject_addr: Addr<ProxyClient> = subject.start();
+        subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+
+        subject_addr.try_send(package).unwrap();
+
+        System::current().stop_with_code(0);
+        system.run();
+        data_cap::set_status(DataCapStatus::Open);
+        assert_eq!(process_package_parameters.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn refuse_to_relay_udp_denied_by_exit_policy() {
+        init_test_logging();
+        let stream_key = make_meaningless_stream_key();
+        let package: ExpiredCoresPackage<ClientUdpRequestPayload_0v1> = ExpiredCoresPackage::new(
+            SocketAddr::from_str("1.2.3.4:1234").unwrap(),
+            Some(make_wallet("consuming")),
+            make_meaningless_route(),
+            ClientUdpRequestPayload_0v1 {
+                stream_key,
+                target_hostname: "tracker.example.org".to_string(),
+                target_port: 6969,
+                data: PlainData::new(b"announce"),
+                originator_public_key: PublicKey::new(&b"originator"[..]),
+            },
+            0,
+        );
+        let system = System::new("refuse_to_relay_udp_denied_by_exit_policy");
+        let peer_actors = peer_actors_builder().build();
+        let mut process_udp_package_parameters = Arc::new(Mutex::new(vec![]));
+        let pool = Box::new(
+            StreamHandlerPoolMock::new()
+                .process_udp_package_parameters(&mut process_udp_package_parameters),
+        );
+        let pool_factory = StreamHandlerPoolFactoryMock::new().make_result(pool);
+        let mut subject = ProxyClient::new(ProxyClientConfig {
+            cryptde: main_cryptde(),
+            dns_servers: dnss(),
+            secure_dns_servers: vec![],
+            dns_cache_size: 0,
+            dns_lookup_threads: 1,
+            exit_policy: ExitPolicy::from_str("category:file-sharing").unwrap(),
+            stream_idle_timeout_opt: None,
+            exit_service_rate: 100,
+            exit_byte_rate: 200,
+        });
+        subject.stream_handler_pool_factory = Box::new(pool_factory);
+        let subject_addr: Addr<ProxyClient> = subject.start();
+        subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+
+        subject_addr.try_send(package).unwrap();
+
+        System::current().stop_with_code(0);
+        system.run();
+        assert_eq!(process_udp_package_parameters.lock().unwrap().len(), 0);
+        TestLogHandler::new().exists_log_containing(&format!(
+            "WARN: ProxyClient: Refusing to provide exit services for stream {}: exit policy says port 6969 is file-sharing traffic",
+            stream_key
+        ));
+    }
+
+    #[test]
+    fn inbound_server_datagram_is_translated_to_cores_package() {
+        let (hopper, _, hopper_recording_arc) = make_recorder();
+        let (accountant, _, accountant_recording_arc) = make_recorder();
+        let stream_key = make_meaningless_stream_key();
+        let system = System::new("inbound_server_datagram_is_translated_to_cores_package");
+        let mut subject = ProxyClient::new(ProxyClientConfig {
+            cryptde: main_cryptde(),
+            dns_servers: dnss(),
+            secure_dns_servers: vec![],
+            dns_cache_size: 0,
+            dns_lookup_threads: 1,
+            exit_policy: ExitPolicy::default(),
+            stream_idle_timeout_opt: None,
+            exit_service_rate: 100,
+            exit_byte_rate: 200,
+        });
+        subject.udp_contexts.insert(
+            stream_key,
+            StreamContext {
+                return_route: make_meaningless_route(),
+                alternate_return_route_opt: None,
+                payload_destination_key: PublicKey::new(&b"abcd"[..]),
+                paying_wallet: Some(make_wallet("paying")),
+            },
+        );
+        let subject_addr: Addr<ProxyClient> = subject.start();
+        let peer_actors = peer_actors_builder()
+            .hopper(hopper)
+            .accountant(accountant)
+            .build();
+        subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+
+        subject_addr
+            .try_send(InboundServerDatagram {
+                stream_key,
+                source: SocketAddr::from_str("5.6.7.8:443").unwrap(),
+                data: b"quic reply".to_vec(),
+            })
+            .unwrap();
+
+        System::current().stop_with_code(0);
+        system.run();
+        let hopper_recording = hopper_recording_arc.lock().unwrap();
+        assert_eq!(
+            hopper_recording.get_record::<IncipientCoresPackage>(0),
+            &IncipientCoresPackage::new(
+                main_cryptde(),
+                make_meaningless_route(),
+                ClientUdpResponsePayload_0v1 {
+                    stream_key,
+                    source: SocketAddr::from_str("5.6.7.8:443").unwrap(),
+                    data: PlainData::new(b"quic reply"),
+                }
+                .into(),
+                &PublicKey::new(&b"abcd"[..]),
+            )
+            .unwrap()
+        );
+        let accountant_recording = accountant_recording_arc.lock().unwrap();
+        assert_eq!(
+            accountant_recording.get_record::<ReportExitServiceProvidedMessage>(0),
+            &ReportExitServiceProvidedMessage {
+                paying_wallet: make_wallet("paying"),
+                payload_size: 10,
+                service_rate: 100,
+                byte_rate: 200,
+            }
+        );
+    }
+
+    #[test]
+    fn inbound_server_data_is_translated_to_cores_packages() {
+        init_test_logging();
+        let (hopper, _, hopper_recording_arc) = make_recorder();
+        let (accountant, _, accountant_recording_arc) = make_recorder();
+        let stream_key = make_meaningless_stream_key();
+        let data: &[u8] = b"An honest politician is one who, when he is bought, will stay bought.";
+        let system = System::new("inbound_server_data_is_translated_to_cores_packages");
+        let mut subject = ProxyClient::new(ProxyClientConfig {
+            cryptde: main_cryptde(),
+            dns_servers: vec![SocketAddr::from_str("8.7.6.5:4321").unwrap()],
+            secure_dns_servers: vec![],
+            dns_cache_size: 0,
+            dns_lookup_threads: 1,
+            exit_policy: ExitPolicy::default(),
+            stream_idle_timeout_opt: None,
+            exit_service_rate: 100,
+            exit_byte_rate: 200,
+        });
+        subject.stream_contexts.insert(
+            stream_key.clone(),
+            StreamContext {
+                return_route: make_meaningless_route(),
+                alternate_return_route_opt: None,
+                payload_destination_key: PublicKey::new(&b"abcd"[..]),
+                paying_wallet: Some(make_wallet("paying")),
+            },
+        );
+        let subject_addr: Addr<ProxyClient> = subject.start();
+        let peer_actors = peer_actors_builder()
+            .hopper(hopper)
+            .accountant(accountant)
+            .build();
+
+        subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+
+        subject_addr
+            .try_send(InboundServerData {
+                stream_key: stream_key.clone(),
+                last_data: false,
+                sequence_number: 1234,
+                source: SocketAddr::from_str("1.2.3.4:5678").unwrap(),
+                data: Vec::from(data),
+            })
+            .unwrap();
+        subject_addr
+            .try_send(InboundServerData {
+                stream_key: stream_key.clone(),
+                last_data: true,
+                sequence_number: 1235,
+                source: SocketAddr::from_str("1.2.3.4:5678").unwrap(),
+                data: Vec::from(data),
+            })
+            .unwrap();
+        subject_addr
+            .try_send(InboundServerData {
+                stream_key: stream_key.clone(),
+                last_data: false,
+                sequence_number: 1236,
+                source: SocketAddr::from_str("1.2.3.4:5678").unwrap(),
+                data: Vec::from(data),
+            })
+            .unwrap();
+        subject_addr
+            .try_send(InboundServerData {
+                stream_key: stream_key.clone(),
+                last_data: true,
+                sequence_number: 1237,
+                source: SocketAddr::from_str("1.2.3.4:5678").unwrap(),
+                data: Vec::from(data),
+            })
+            .unwrap();
+
+        System::current().stop_with_code(0);
+        system.run();
+        let hopper_recording = hopper_recording_arc.lock().unwrap();
+        assert_eq!(
+            hopper_recording.get_record::<IncipientCoresPackage>(0),
+            &IncipientCoresPackage::new(
+                main_cryptde(),
+                make_meaningless_route(),
+                MessageType::ClientResponse(VersionedData::new(
+                    &crate::sub_lib::migrations::client_response_payload::MIGRATIONS,
+                    &ClientResponsePayload_0v1 {
                         stream_key: stream_key.clone(),
                         sequenced_packet: SequencedPacket {
                             data: Vec::from(data),
@@ -970,18 +2286,102 @@ mod tests {
             }
         );
         assert_eq!(
-            accountant_recording.get_record::<ReportExitServiceProvidedMessage>(1),
-            &ReportExitServiceProvidedMessage {
-                paying_wallet: make_wallet("paying"),
-                payload_size: data.len(),
-                service_rate: 100,
-                byte_rate: 200,
+            accountant_recording.get_record::<ReportExitServiceProvidedMessage>(1),
+            &ReportExitServiceProvidedMessage {
+                paying_wallet: make_wallet("paying"),
+                payload_size: data.len(),
+                service_rate: 100,
+                byte_rate: 200,
+            }
+        );
+        assert_eq!(accountant_recording.len(), 2);
+        let tlh = TestLogHandler::new();
+        tlh.exists_log_containing(format!("ERROR: ProxyClient: Received InboundServerData from 1.2.3.4:5678: stream +dKB2Lsh3ET2TS/J/cexaanFQz4, sequence 1236, length {}; but no such known stream - ignoring", data.len()).as_str());
+        tlh.exists_log_containing(format!("ERROR: ProxyClient: Received InboundServerData (last_data) from 1.2.3.4:5678: stream +dKB2Lsh3ET2TS/J/cexaanFQz4, sequence 1237, length {}; but no such known stream - ignoring", data.len()).as_str());
+    }
+
+    #[test]
+    fn streams_query_is_answered_with_consumed_and_served_streams() {
+        let (hopper, _, _) = make_recorder();
+        let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
+        let stream_key = make_meaningless_stream_key();
+        let data: &[u8] = b"An honest politician is one who, when he is bought, will stay bought.";
+        let system = System::new("streams_query_is_answered_with_consumed_and_served_streams");
+        let mut subject = ProxyClient::new(ProxyClientConfig {
+            cryptde: main_cryptde(),
+            dns_servers: vec![SocketAddr::from_str("8.7.6.5:4321").unwrap()],
+            secure_dns_servers: vec![],
+            dns_cache_size: 0,
+            dns_lookup_threads: 1,
+            exit_policy: ExitPolicy::default(),
+            stream_idle_timeout_opt: None,
+            exit_service_rate: 100,
+            exit_byte_rate: 200,
+        });
+        subject.stream_contexts.insert(
+            stream_key,
+            StreamContext {
+                return_route: make_meaningless_route(),
+                alternate_return_route_opt: None,
+                payload_destination_key: PublicKey::new(&b"abcd"[..]),
+                paying_wallet: Some(make_wallet("paying")),
+            },
+        );
+        let mut stats = StreamStats::new(
+            ProxyProtocol::TLS,
+            Some("example.com".to_string()),
+            Instant::now(),
+        );
+        stats.bytes_out = 517;
+        subject.served_streams.insert(stream_key, stats);
+        let consuming = vec![StreamStats::new(ProxyProtocol::HTTP, None, Instant::now())
+            .to_ui_stream(&make_meaningless_stream_key(), Instant::now())];
+        let subject_addr: Addr<ProxyClient> = subject.start();
+        let peer_actors = peer_actors_builder()
+            .hopper(hopper)
+            .ui_gateway(ui_gateway)
+            .build();
+        subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+
+        subject_addr
+            .try_send(InboundServerData {
+                stream_key,
+                last_data: false,
+                sequence_number: 0,
+                source: SocketAddr::from_str("1.2.3.4:5678").unwrap(),
+                data: Vec::from(data),
+            })
+            .unwrap();
+        subject_addr
+            .try_send(StreamsQuery {
+                client_id: 1234,
+                context_id: 4321,
+                consuming: consuming.clone(),
+            })
+            .unwrap();
+
+        System::current().stop_with_code(0);
+        system.run();
+        let ui_gateway_recording = ui_gateway_recording_arc.lock().unwrap();
+        assert_eq!(
+            ui_gateway_recording.get_record::<NodeToUiMessage>(0),
+            &NodeToUiMessage {
+                target: MessageTarget::ClientId(1234),
+                body: UiStreamsResponse {
+                    consuming,
+                    serving: vec![UiStream {
+                        stream_key: stream_key.to_string(),
+                        protocol: "TLS".to_string(),
+                        target_host_opt: Some("example.com".to_string()),
+                        bytes_out: 517,
+                        bytes_in: data.len() as u64,
+                        hop_count_opt: None,
+                        age_seconds: 0,
+                    }],
+                }
+                .tmb(4321),
             }
         );
-        assert_eq!(accountant_recording.len(), 2);
-        let tlh = TestLogHandler::new();
-        tlh.exists_log_containing(format!("ERROR: ProxyClient: Received InboundServerData from 1.2.3.4:5678: stream +dKB2Lsh3ET2TS/J/cexaanFQz4, sequence 1236, length {}; but no such known stream - ignoring", data.len()).as_str());
-        tlh.exists_log_containing(format!("ERROR: ProxyClient: Received InboundServerData (last_data) from 1.2.3.4:5678: stream +dKB2Lsh3ET2TS/J/cexaanFQz4, sequence 1237, length {}; but no such known stream - ignoring", data.len()).as_str());
     }
 
     #[test]
@@ -994,6 +2394,11 @@ mod tests {
         let mut subject = ProxyClient::new(ProxyClientConfig {
             cryptde: main_cryptde(),
             dns_servers: vec![SocketAddr::from_str("8.7.6.5:4321").unwrap()],
+            secure_dns_servers: vec![],
+            dns_cache_size: 0,
+            dns_lookup_threads: 1,
+            exit_policy: ExitPolicy::default(),
+            stream_idle_timeout_opt: None,
             exit_service_rate: 100,
             exit_byte_rate: 200,
         });
@@ -1001,6 +2406,7 @@ mod tests {
             stream_key.clone(),
             StreamContext {
                 return_route: make_meaningless_route(),
+                alternate_return_route_opt: None,
                 payload_destination_key: PublicKey::new(&b"abcd"[..]),
                 paying_wallet: None,
             },
@@ -1044,6 +2450,11 @@ mod tests {
         let mut subject = ProxyClient::new(ProxyClientConfig {
             cryptde: main_cryptde(),
             dns_servers: vec![SocketAddr::from_str("8.7.6.5:4321").unwrap()],
+            secure_dns_servers: vec![],
+            dns_cache_size: 0,
+            dns_lookup_threads: 1,
+            exit_policy: ExitPolicy::default(),
+            stream_idle_timeout_opt: None,
             exit_service_rate: 100,
             exit_byte_rate: 200,
         });
@@ -1051,6 +2462,7 @@ mod tests {
             stream_key.clone(),
             StreamContext {
                 return_route: make_meaningless_route(),
+                alternate_return_route_opt: None,
                 payload_destination_key: PublicKey::new(&[]),
                 paying_wallet: Some(make_wallet("consuming")),
             },
@@ -1078,7 +2490,495 @@ mod tests {
         assert_eq!(hopper_recording.len(), 0);
         let accountant_recording = accountant_recording_arc.lock().unwrap();
         assert_eq!(accountant_recording.len(), 0);
-        TestLogHandler::new().exists_log_containing(format!("ERROR: ProxyClient: Could not create CORES package for {}-byte response from 1.2.3.4:5678, seq 1234: Could not encrypt payload: EncryptionError(EmptyKey) - ignoring", data.len()).as_str());
+        TestLogHandler::new().exists_log_containing(format!("ERROR: ProxyClient: Could not create CORES package for {}-byte response on stream +dKB2Lsh3ET2TS/J/cexaanFQz4, seq 1234: Could not encrypt payload: EncryptionError(EmptyKey) - ignoring", data.len()).as_str());
+    }
+
+    fn make_response_icp(
+        stream_key: StreamKey,
+        sequence_number: u64,
+        last_data: bool,
+    ) -> IncipientCoresPackage {
+        IncipientCoresPackage::new(
+            main_cryptde(),
+            make_meaningless_route(),
+            MessageType::ClientResponse(VersionedData::new(
+                &crate::sub_lib::migrations::client_response_payload::MIGRATIONS,
+                &ClientResponsePayload_0v1 {
+                    stream_key,
+                    sequenced_packet: SequencedPacket::new(
+                        vec![sequence_number as u8],
+                        sequence_number,
+                        last_data,
+                    ),
+                },
+            )),
+            &PublicKey::new(&b"abcd"[..]),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn inbound_server_data_beyond_send_window_waits_for_acknowledgment() {
+        init_test_logging();
+        let (hopper, _, hopper_recording_arc) = make_recorder();
+        let (accountant, _, accountant_recording_arc) = make_recorder();
+        let stream_key = make_meaningless_stream_key();
+        let system = System::new("inbound_server_data_beyond_send_window_waits_for_acknowledgment");
+        let mut subject = ProxyClient::new(ProxyClientConfig {
+            cryptde: main_cryptde(),
+            dns_servers: vec![SocketAddr::from_str("8.7.6.5:4321").unwrap()],
+            secure_dns_servers: vec![],
+            dns_cache_size: 0,
+            dns_lookup_threads: 1,
+            exit_policy: ExitPolicy::default(),
+            stream_idle_timeout_opt: None,
+            exit_service_rate: 100,
+            exit_byte_rate: 200,
+        });
+        subject.stream_contexts.insert(
+            stream_key,
+            StreamContext {
+                return_route: make_meaningless_route(),
+                alternate_return_route_opt: None,
+                payload_destination_key: PublicKey::new(&b"abcd"[..]),
+                paying_wallet: Some(make_wallet("paying")),
+            },
+        );
+        subject.send_window_size = 2;
+        let subject_addr: Addr<ProxyClient> = subject.start();
+        let peer_actors = peer_actors_builder()
+            .hopper(hopper)
+            .accountant(accountant)
+            .build();
+        subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+        let packet_count = 4;
+        let ack = |next_sequence_number| {
+            ExpiredCoresPackage::new(
+                SocketAddr::from_str("1.2.3.4:1234").unwrap(),
+                None,
+                make_meaningless_route(),
+                StreamAckPayload_0v1 {
+                    stream_key,
+                    next_sequence_number,
+                },
+                0,
+            )
+        };
+
+        (0..packet_count).for_each(|sequence_number| {
+            subject_addr
+                .try_send(InboundServerData {
+                    stream_key,
+                    last_data: sequence_number == packet_count - 1,
+                    sequence_number,
+                    source: SocketAddr::from_str("1.2.3.4:5678").unwrap(),
+                    data: vec![sequence_number as u8],
+                })
+                .unwrap()
+        });
+        subject_addr.try_send(ack(2)).unwrap();
+        subject_addr.try_send(ack(packet_count)).unwrap();
+        subject_addr
+            .try_send(InboundServerData {
+                stream_key,
+                last_data: false,
+                sequence_number: packet_count,
+                source: SocketAddr::from_str("1.2.3.4:5678").unwrap(),
+                data: vec![],
+            })
+            .unwrap();
+
+        System::current().stop_with_code(0);
+        system.run();
+        let hopper_recording = hopper_recording_arc.lock().unwrap();
+        (0..packet_count).for_each(|sequence_number| {
+            assert_eq!(
+                hopper_recording.get_record::<IncipientCoresPackage>(sequence_number as usize),
+                &make_response_icp(
+                    stream_key,
+                    sequence_number,
+                    sequence_number == packet_count - 1
+                )
+            )
+        });
+        assert_eq!(hopper_recording.len(), packet_count as usize);
+        let accountant_recording = accountant_recording_arc.lock().unwrap();
+        assert_eq!(accountant_recording.len(), packet_count as usize);
+        let tlh = TestLogHandler::new();
+        tlh.exists_log_containing(&format!(
+            "DEBUG: ProxyClient: Holding packet 2 of stream {} until the exit window opens",
+            stream_key
+        ));
+        tlh.exists_log_containing(&format!(
+            "DEBUG: ProxyClient: Retiring stream key {}: all data acknowledged",
+            stream_key
+        ));
+        tlh.exists_log_containing(&format!(
+            "ERROR: ProxyClient: Received InboundServerData from 1.2.3.4:5678: stream {}, sequence {}, length 0; but no such known stream - ignoring",
+            stream_key, packet_count
+        ));
+    }
+
+    #[test]
+    fn overdue_responses_are_retransmitted_without_reporting_exit_service_again() {
+        let system = System::new("overdue_responses_are_retransmitted");
+        let (hopper, _, hopper_recording_arc) = make_recorder();
+        let (accountant, _, accountant_recording_arc) = make_recorder();
+        let stream_key = make_meaningless_stream_key();
+        let mut subject = ProxyClient::new(ProxyClientConfig {
+            cryptde: main_cryptde(),
+            dns_servers: vec![SocketAddr::from_str("8.7.6.5:4321").unwrap()],
+            secure_dns_servers: vec![],
+            dns_cache_size: 0,
+            dns_lookup_threads: 1,
+            exit_policy: ExitPolicy::default(),
+            stream_idle_timeout_opt: None,
+            exit_service_rate: 100,
+            exit_byte_rate: 200,
+        });
+        subject.to_hopper = Some(hopper.start().recipient());
+        subject.to_accountant = Some(accountant.start().recipient());
+        subject.stream_contexts.insert(
+            stream_key,
+            StreamContext {
+                return_route: make_meaningless_route(),
+                alternate_return_route_opt: None,
+                payload_destination_key: PublicKey::new(&b"abcd"[..]),
+                paying_wallet: Some(make_wallet("paying")),
+            },
+        );
+        let then = Instant::now();
+        let mut window = SendWindow::new(SEND_WINDOW_SIZE);
+        window.offer(SequencedPacket::new(vec![0], 0, false), then);
+        window.offer(SequencedPacket::new(vec![1], 1, false), then);
+        window.acknowledge(1, then);
+        subject.send_windows.insert(stream_key, window);
+
+        subject.retransmit_overdue_packets(then + RETRANSMIT_TIMEOUT);
+
+        System::current().stop_with_code(0);
+        system.run();
+        let hopper_recording = hopper_recording_arc.lock().unwrap();
+        assert_eq!(
+            hopper_recording.get_record::<IncipientCoresPackage>(0),
+            &make_response_icp(stream_key, 1, false)
+        );
+        assert_eq!(hopper_recording.len(), 1);
+        let accountant_recording = accountant_recording_arc.lock().unwrap();
+        assert_eq!(accountant_recording.len(), 0);
+    }
+
+    #[test]
+    fn backed_up_stream_stops_reading_until_acknowledgments_catch_up() {
+        init_test_logging();
+        let stream_key = make_meaningless_stream_key();
+        let mut subject = ProxyClient::new(ProxyClientConfig {
+            cryptde: main_cryptde(),
+            dns_servers: vec![SocketAddr::from_str("8.7.6.5:4321").unwrap()],
+            secure_dns_servers: vec![],
+            dns_cache_size: 0,
+            dns_lookup_threads: 1,
+            exit_policy: ExitPolicy::default(),
+            stream_idle_timeout_opt: None,
+            exit_service_rate: 100,
+            exit_byte_rate: 200,
+        });
+        let now = Instant::now();
+        let mut window = SendWindow::new(1);
+        window.offer(SequencedPacket::new(vec![0], 0, false), now);
+        window.offer(
+            SequencedPacket::new(vec![1; PAUSE_READING_BYTES], 1, false),
+            now,
+        );
+        subject.send_windows.insert(stream_key, window);
+        let gate = subject.read_gates.open(stream_key);
+
+        subject.apply_backpressure(&stream_key);
+        let paused_while_backed_up = gate.is_paused();
+        subject
+            .send_windows
+            .get_mut(&stream_key)
+            .unwrap()
+            .acknowledge(1, now);
+        subject.apply_backpressure(&stream_key);
+
+        assert_eq!(paused_while_backed_up, true);
+        assert_eq!(gate.is_paused(), false);
+        let tlh = TestLogHandler::new();
+        tlh.exists_log_containing(&format!(
+            "DEBUG: ProxyClient: Pausing reads on stream {}: {} bytes are waiting to go back",
+            stream_key, PAUSE_READING_BYTES
+        ));
+        tlh.exists_log_containing(&format!(
+            "DEBUG: ProxyClient: Resuming reads on stream {}: 0 bytes are waiting to go back",
+            stream_key
+        ));
+    }
+
+    fn make_route_through(relay: &[u8]) -> Route {
+        Route::one_way(
+            RouteSegment::new(
+                vec![&PublicKey::new(relay), &PublicKey::new(&b"exit"[..])],
+                Component::ProxyClient,
+            ),
+            main_cryptde(),
+            Some(make_paying_wallet(b"irrelevant")),
+            Some(ROPSTEN_TESTNET_CONTRACT_ADDRESS),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn request_over_a_different_route_becomes_the_alternate_return_route() {
+        let stream_key = make_meaningless_stream_key();
+        let mut subject = ProxyClient::new(ProxyClientConfig {
+            cryptde: main_cryptde(),
+            dns_servers: vec![SocketAddr::from_str("8.7.6.5:4321").unwrap()],
+            secure_dns_servers: vec![],
+            dns_cache_size: 0,
+            dns_lookup_threads: 1,
+            exit_policy: ExitPolicy::default(),
+            stream_idle_timeout_opt: None,
+            exit_service_rate: 100,
+            exit_byte_rate: 200,
+        });
+        let key = PublicKey::new(&b"abcd"[..]);
+
+        let first = subject.updated_stream_context(
+            &stream_key,
+            make_route_through(b"one"),
+            key.clone(),
+            None,
+        );
+        subject.stream_contexts.insert(stream_key, first);
+        let second = subject.updated_stream_context(
+            &stream_key,
+            make_route_through(b"two"),
+            key.clone(),
+            None,
+        );
+        subject.stream_contexts.insert(stream_key, second);
+        let third =
+            subject.updated_stream_context(&stream_key, make_route_through(b"one"), key, None);
+
+        let context = subject.stream_contexts.get(&stream_key).unwrap();
+        assert_eq!(context.return_route, make_route_through(b"two"));
+        assert_eq!(
+            context.alternate_return_route_opt,
+            Some(make_route_through(b"one"))
+        );
+        assert_eq!(third.return_route, make_route_through(b"one"));
+        assert_eq!(
+            third.alternate_return_route_opt,
+            Some(make_route_through(b"two"))
+        );
+    }
+
+    fn make_resumption_package(
+        stream_key: StreamKey,
+        route: Route,
+    ) -> ExpiredCoresPackage<ClientRequestPayload_0v1> {
+        ExpiredCoresPackage::new(
+            SocketAddr::from_str("1.2.3.4:1234").unwrap(),
+            Some(make_wallet("consuming")),
+            route,
+            ClientRequestPayload_0v1 {
+                stream_key,
+                sequenced_packet: SequencedPacket::new(
+                    b"GET / HTTP/1.1\r\n\r\n".to_vec(),
+                    3,
+                    false,
+                ),
+                target_hostname: Some("example.com".to_string()),
+                target_port: 80,
+                protocol: ProxyProtocol::HTTP,
+                originator_public_key: PublicKey::new(&b"originator"[..]),
+                is_resumption: true,
+            },
+            0,
+        )
+    }
+
+    #[test]
+    fn resumption_of_an_open_stream_replaces_both_of_its_routes() {
+        let stream_key = make_meaningless_stream_key();
+        let mut process_package_parameters = Arc::new(Mutex::new(vec![]));
+        let mut subject = ProxyClient::new(ProxyClientConfig {
+            cryptde: main_cryptde(),
+            dns_servers: dnss(),
+            secure_dns_servers: vec![],
+            dns_cache_size: 0,
+            dns_lookup_threads: 1,
+            exit_policy: ExitPolicy::default(),
+            stream_idle_timeout_opt: None,
+            exit_service_rate: 100,
+            exit_byte_rate: 200,
+        });
+        subject.pool = Some(Box::new(
+            StreamHandlerPoolMock::new()
+                .process_package_parameters(&mut process_package_parameters),
+        ));
+        subject.stream_contexts.insert(
+            stream_key,
+            StreamContext {
+                return_route: make_route_through(b"one"),
+                alternate_return_route_opt: Some(make_route_through(b"two")),
+                payload_destination_key: PublicKey::new(&b"originator"[..]),
+                paying_wallet: Some(make_wallet("consuming")),
+            },
+        );
+
+        subject.handle_client_request_payload(make_resumption_package(
+            stream_key,
+            make_route_through(b"three"),
+        ));
+
+        let context = subject.stream_contexts.get(&stream_key).unwrap();
+        assert_eq!(context.return_route, make_route_through(b"three"));
+        assert_eq!(context.alternate_return_route_opt, None);
+        assert_eq!(process_package_parameters.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn resumption_of_a_stream_that_is_gone_is_refused_with_an_empty_last_packet() {
+        init_test_logging();
+        let system = System::new("resumption_of_a_stream_that_is_gone_is_refused");
+        let (hopper, _, hopper_recording_arc) = make_recorder();
+        let stream_key = make_meaningless_stream_key();
+        let mut process_package_parameters = Arc::new(Mutex::new(vec![]));
+        let mut subject = ProxyClient::new(ProxyClientConfig {
+            cryptde: main_cryptde(),
+            dns_servers: dnss(),
+            secure_dns_servers: vec![],
+            dns_cache_size: 0,
+            dns_lookup_threads: 1,
+            exit_policy: ExitPolicy::default(),
+            stream_idle_timeout_opt: None,
+            exit_service_rate: 100,
+            exit_byte_rate: 200,
+        });
+        subject.to_hopper = Some(hopper.start().recipient());
+        subject.pool = Some(Box::new(
+            StreamHandlerPoolMock::new()
+                .process_package_parameters(&mut process_package_parameters),
+        ));
+
+        subject.handle_client_request_payload(make_resumption_package(
+            stream_key,
+            make_meaningless_route(),
+        ));
+
+        System::current().stop_with_code(0);
+        system.run();
+        let hopper_recording = hopper_recording_arc.lock().unwrap();
+        assert_eq!(
+            hopper_recording.get_record::<IncipientCoresPackage>(0),
+            &IncipientCoresPackage::new(
+                main_cryptde(),
+                make_meaningless_route(),
+                MessageType::ClientResponse(VersionedData::new(
+                    &crate::sub_lib::migrations::client_response_payload::MIGRATIONS,
+                    &ClientResponsePayload_0v1 {
+                        stream_key,
+                        sequenced_packet: SequencedPacket::new(vec![], 0, true),
+                    },
+                )),
+                &PublicKey::new(&b"originator"[..]),
+            )
+            .unwrap()
+        );
+        assert_eq!(process_package_parameters.lock().unwrap().len(), 0);
+        assert_eq!(subject.stream_contexts.contains_key(&stream_key), false);
+        TestLogHandler::new().exists_log_containing(&format!(
+            "INFO: ProxyClient: Can't resume stream {}: it's no longer open here",
+            stream_key
+        ));
+    }
+
+    #[test]
+    fn responses_alternate_between_routes_and_retransmissions_take_the_other_one() {
+        let one_route = StreamContext {
+            return_route: make_route_through(b"one"),
+            alternate_return_route_opt: None,
+            payload_destination_key: PublicKey::new(&b"abcd"[..]),
+            paying_wallet: None,
+        };
+        let two_routes = StreamContext {
+            return_route: make_route_through(b"one"),
+            alternate_return_route_opt: Some(make_route_through(b"two")),
+            payload_destination_key: PublicKey::new(&b"abcd"[..]),
+            paying_wallet: None,
+        };
+
+        assert_eq!(
+            one_route.return_route_for(1, true),
+            &make_route_through(b"one")
+        );
+        assert_eq!(
+            two_routes.return_route_for(0, false),
+            &make_route_through(b"one")
+        );
+        assert_eq!(
+            two_routes.return_route_for(1, false),
+            &make_route_through(b"two")
+        );
+        assert_eq!(
+            two_routes.return_route_for(0, true),
+            &make_route_through(b"two")
+        );
+        assert_eq!(
+            two_routes.return_route_for(1, true),
+            &make_route_through(b"one")
+        );
+    }
+
+    #[test]
+    fn stream_is_abandoned_when_responses_go_unacknowledged() {
+        init_test_logging();
+        let system = System::new("stream_is_abandoned_when_responses_go_unacknowledged");
+        let (hopper, _, hopper_recording_arc) = make_recorder();
+        let stream_key = make_meaningless_stream_key();
+        let mut subject = ProxyClient::new(ProxyClientConfig {
+            cryptde: main_cryptde(),
+            dns_servers: vec![SocketAddr::from_str("8.7.6.5:4321").unwrap()],
+            secure_dns_servers: vec![],
+            dns_cache_size: 0,
+            dns_lookup_threads: 1,
+            exit_policy: ExitPolicy::default(),
+            stream_idle_timeout_opt: None,
+            exit_service_rate: 100,
+            exit_byte_rate: 200,
+        });
+        subject.to_hopper = Some(hopper.start().recipient());
+        subject.stream_contexts.insert(
+            stream_key,
+            StreamContext {
+                return_route: make_meaningless_route(),
+                alternate_return_route_opt: None,
+                payload_destination_key: PublicKey::new(&b"abcd"[..]),
+                paying_wallet: None,
+            },
+        );
+        let mut now = Instant::now();
+        let mut window = SendWindow::new(SEND_WINDOW_SIZE);
+        window.offer(SequencedPacket::new(vec![0], 0, false), now);
+        subject.send_windows.insert(stream_key, window);
+
+        for _ in 0..=MAX_RETRANSMISSIONS {
+            now += RETRANSMIT_TIMEOUT;
+            subject.retransmit_overdue_packets(now);
+        }
+
+        assert!(subject.send_windows.is_empty());
+        assert!(subject.stream_contexts.is_empty());
+        System::current().stop_with_code(0);
+        system.run();
+        let hopper_recording = hopper_recording_arc.lock().unwrap();
+        assert_eq!(hopper_recording.len(), MAX_RETRANSMISSIONS as usize);
+        TestLogHandler::new().exists_log_containing(&format!(
+            "WARN: ProxyClient: Abandoning stream {}: a packet went unacknowledged after {} retransmissions",
+            stream_key, MAX_RETRANSMISSIONS
+        ));
     }
 
     #[test]
@@ -1092,6 +2992,11 @@ mod tests {
         let mut subject = ProxyClient::new(ProxyClientConfig {
             cryptde,
             dns_servers: vec![SocketAddr::from_str("8.7.6.5:4321").unwrap()],
+            secure_dns_servers: vec![],
+            dns_cache_size: 0,
+            dns_lookup_threads: 1,
+            exit_policy: ExitPolicy::default(),
+            stream_idle_timeout_opt: None,
             exit_service_rate: 100,
             exit_byte_rate: 200,
         });
@@ -1108,6 +3013,7 @@ mod tests {
             stream_key.clone(),
             StreamContext {
                 return_route: old_return_route,
+                alternate_return_route_opt: None,
                 payload_destination_key: originator_public_key.clone(),
                 paying_wallet: Some(make_wallet("consuming")),
             },
@@ -1130,10 +3036,11 @@ mod tests {
             target_port: 0,
             protocol: ProxyProtocol::HTTP,
             originator_public_key: originator_public_key.clone(),
+            is_resumption: false,
         };
 
         subject_addr
-            .try_send(ExpiredCoresPackage::new(
+            .try_send(ExpiredCoresPackage::<ClientRequestPayload_0v1>::new(
                 SocketAddr::from_str("2.3.4.5:1235").unwrap(),
                 Some(make_wallet("gnimusnoc")),
                 new_return_route.clone(),
@@ -0,0 +1,179 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use crate::proxy_client::smtp_protocol_pack::SmtpExitProtocolPack;
+use crate::sub_lib::proxy_server::ProxyProtocol;
+
+// A protocol pack tells the ProxyClient what's special about carrying one protocol to and from
+// its servers. Each stream gets its own framer and handler, so they can keep state between
+// packets; the framer sees request data in sequence order, and the handler sees response data
+// in the order it's read from the server.
+pub trait ExitProtocolPack: Send + Sync {
+    fn proxy_protocol(&self) -> ProxyProtocol;
+    // If so, a stream is opened for a request with no data, so that the client can hear from
+    // the server before it says anything
+    fn server_speaks_first(&self) -> bool;
+    fn request_framer(&self) -> Box<dyn RequestFramer>;
+    fn response_handler(&self) -> Box<dyn ResponseHandler>;
+}
+
+// Turns request data from the originating Node into what's written to the server
+pub trait RequestFramer: Send {
+    fn frame(&mut self, data: Vec<u8>) -> Vec<u8>;
+}
+
+// Turns data read from the server into what goes back to the originating Node
+pub trait ResponseHandler: Send {
+    fn handle(&mut self, data: Vec<u8>) -> Vec<u8>;
+}
+
+pub struct Passthrough {}
+
+impl RequestFramer for Passthrough {
+    fn frame(&mut self, data: Vec<u8>) -> Vec<u8> {
+        data
+    }
+}
+
+impl ResponseHandler for Passthrough {
+    fn handle(&mut self, data: Vec<u8>) -> Vec<u8> {
+        data
+    }
+}
+
+// HTTP and TLS go to their servers exactly as the client sent them
+pub struct PassthroughExitProtocolPack {
+    pub proxy_protocol: ProxyProtocol,
+}
+
+impl ExitProtocolPack for PassthroughExitProtocolPack {
+    fn proxy_protocol(&self) -> ProxyProtocol {
+        self.proxy_protocol
+    }
+
+    fn server_speaks_first(&self) -> bool {
+        false
+    }
+
+    fn request_framer(&self) -> Box<dyn RequestFramer> {
+        Box::new(Passthrough {})
+    }
+
+    fn response_handler(&self) -> Box<dyn ResponseHandler> {
+        Box::new(Passthrough {})
+    }
+}
+
+pub struct ProtocolPackRegistry {
+    packs: Vec<Box<dyn ExitProtocolPack>>,
+}
+
+impl Default for ProtocolPackRegistry {
+    fn default() -> Self {
+        let mut registry = ProtocolPackRegistry { packs: vec![] };
+        registry.register(Box::new(PassthroughExitProtocolPack {
+            proxy_protocol: ProxyProtocol::HTTP,
+        }));
+        registry.register(Box::new(PassthroughExitProtocolPack {
+            proxy_protocol: ProxyProtocol::TLS,
+        }));
+        registry.register(Box::new(SmtpExitProtocolPack {}));
+        registry
+    }
+}
+
+impl ProtocolPackRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Replaces any pack already registered for the same protocol
+    pub fn register(&mut self, pack: Box<dyn ExitProtocolPack>) {
+        let proxy_protocol = pack.proxy_protocol();
+        self.packs
+            .retain(|existing| existing.proxy_protocol() != proxy_protocol);
+        self.packs.push(pack);
+    }
+
+    pub fn pack_for(&self, proxy_protocol: ProxyProtocol) -> &dyn ExitProtocolPack {
+        self.packs
+            .iter()
+            .find(|pack| pack.proxy_protocol() == proxy_protocol)
+            .unwrap_or_else(|| panic!("No protocol pack registered for {:?}", proxy_protocol))
+            .as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct ShoutingFramer {}
+
+    impl RequestFramer for ShoutingFramer {
+        fn frame(&mut self, data: Vec<u8>) -> Vec<u8> {
+            data.to_ascii_uppercase()
+        }
+    }
+
+    struct ShoutingExitProtocolPack {}
+
+    impl ExitProtocolPack for ShoutingExitProtocolPack {
+        fn proxy_protocol(&self) -> ProxyProtocol {
+            ProxyProtocol::HTTP
+        }
+
+        fn server_speaks_first(&self) -> bool {
+            true
+        }
+
+        fn request_framer(&self) -> Box<dyn RequestFramer> {
+            Box::new(ShoutingFramer {})
+        }
+
+        fn response_handler(&self) -> Box<dyn ResponseHandler> {
+            Box::new(Passthrough {})
+        }
+    }
+
+    #[test]
+    fn registry_starts_with_a_pack_for_every_protocol() {
+        let subject = ProtocolPackRegistry::new();
+
+        vec![ProxyProtocol::HTTP, ProxyProtocol::TLS, ProxyProtocol::SMTP]
+            .into_iter()
+            .for_each(|proxy_protocol| {
+                assert_eq!(
+                    subject.pack_for(proxy_protocol).proxy_protocol(),
+                    proxy_protocol
+                )
+            });
+        assert_eq!(
+            subject.pack_for(ProxyProtocol::HTTP).server_speaks_first(),
+            false
+        );
+        assert_eq!(
+            subject
+                .pack_for(ProxyProtocol::TLS)
+                .request_framer()
+                .frame(b"hello".to_vec()),
+            b"hello".to_vec()
+        );
+    }
+
+    #[test]
+    fn registered_pack_replaces_the_one_for_its_protocol() {
+        let mut subject = ProtocolPackRegistry::new();
+
+        subject.register(Box::new(ShoutingExitProtocolPack {}));
+
+        let pack = subject.pack_for(ProxyProtocol::HTTP);
+        assert_eq!(pack.server_speaks_first(), true);
+        assert_eq!(
+            pack.request_framer().frame(b"hello".to_vec()),
+            b"HELLO".to_vec()
+        );
+        assert_eq!(
+            subject.pack_for(ProxyProtocol::TLS).server_speaks_first(),
+            false
+        );
+    }
+}
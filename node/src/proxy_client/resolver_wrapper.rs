@@ -1,4 +1,8 @@
 // Copyright (c) 2017-2019, Substratum LLC (https://substratum.net) and/or its affiliates. All rights reserved.
+use crate::proxy_client::secure_resolver::{
+    SecureDnsTransportReal, SecureResolverWrapper, SECURE_DNS_TIMEOUT,
+};
+use masq_lib::secure_dns::SecureDnsServer;
 use tokio::prelude::Future;
 use trust_dns_resolver::config::ResolverConfig;
 use trust_dns_resolver::config::ResolverOpts;
@@ -14,6 +18,11 @@ pub trait ResolverWrapper: Send {
 
 pub trait ResolverWrapperFactory {
     fn make(&self, config: ResolverConfig, options: ResolverOpts) -> Box<dyn ResolverWrapper>;
+    fn make_secure(
+        &self,
+        servers: Vec<SecureDnsServer>,
+        threads: usize,
+    ) -> Box<dyn ResolverWrapper>;
 }
 
 pub struct ResolverWrapperReal {
@@ -35,4 +44,16 @@ impl ResolverWrapperFactory for ResolverWrapperFactoryReal {
 
         Box::new(ResolverWrapperReal { delegate })
     }
+
+    fn make_secure(
+        &self,
+        servers: Vec<SecureDnsServer>,
+        threads: usize,
+    ) -> Box<dyn ResolverWrapper> {
+        Box::new(SecureResolverWrapper::new(
+            servers,
+            Box::new(SecureDnsTransportReal::new(SECURE_DNS_TIMEOUT)),
+            threads,
+        ))
+    }
 }
@@ -0,0 +1,795 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use crate::proxy_client::resolver_wrapper::ResolverWrapper;
+use crate::proxy_client::resolver_wrapper::WrappedLookupIpFuture;
+use crate::sub_lib::deterministic;
+use crate::sub_lib::logger::Logger;
+use futures::future;
+use futures::sync::oneshot;
+use futures::Future;
+use masq_lib::secure_dns::{SecureDnsProtocol, SecureDnsServer};
+use native_tls::TlsConnector;
+use std::collections::HashMap;
+use std::io;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::str::FromStr;
+use std::sync::mpsc::{Receiver, SyncSender, TrySendError};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use trust_dns_resolver::error::{ResolveError, ResolveErrorKind};
+use trust_dns_resolver::lookup::Lookup;
+use trust_dns_resolver::lookup_ip::LookupIp;
+use trust_dns_resolver::proto::op::{Message, MessageType, OpCode, Query, ResponseCode};
+use trust_dns_resolver::proto::rr::{Name, RData, Record, RecordType};
+
+pub const SECURE_DNS_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_CACHE_ENTRIES: usize = 1000;
+const MAX_CACHE_TTL: Duration = Duration::from_secs(3600);
+const MAX_HTTPS_RESPONSE_LEN: usize = 65536 + 8192;
+const MAX_WAITING_LOOKUPS: usize = 64;
+
+pub trait SecureDnsTransport: Send + Sync {
+    fn exchange(&self, server: &SecureDnsServer, request: &[u8]) -> io::Result<Vec<u8>>;
+}
+
+pub struct SecureDnsTransportReal {
+    timeout: Duration,
+}
+
+impl SecureDnsTransport for SecureDnsTransportReal {
+    fn exchange(&self, server: &SecureDnsServer, request: &[u8]) -> io::Result<Vec<u8>> {
+        let address = match (server.host.as_str(), server.port)
+            .to_socket_addrs()?
+            .next()
+        {
+            Some(address) => address,
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("Could not find an address for {}", server.host),
+                ))
+            }
+        };
+        let tcp_stream = TcpStream::connect_timeout(&address, self.timeout)?;
+        tcp_stream.set_read_timeout(Some(self.timeout))?;
+        tcp_stream.set_write_timeout(Some(self.timeout))?;
+        let connector = TlsConnector::new()
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("{}", e)))?;
+        let mut tls_stream = connector.connect(&server.host, tcp_stream).map_err(|e| {
+            io::Error::new(io::ErrorKind::Other, format!("TLS handshake failed: {}", e))
+        })?;
+        match server.protocol {
+            SecureDnsProtocol::Tls => {
+                tls_stream.write_all(&tls_frame(request))?;
+                let mut length_bytes = [0u8; 2];
+                tls_stream.read_exact(&mut length_bytes)?;
+                let mut response = vec![0u8; u16::from_be_bytes(length_bytes) as usize];
+                tls_stream.read_exact(&mut response)?;
+                Ok(response)
+            }
+            SecureDnsProtocol::Https => {
+                tls_stream.write_all(&https_request(server, request))?;
+                Self::read_https_response(&mut tls_stream)
+            }
+        }
+    }
+}
+
+impl SecureDnsTransportReal {
+    pub fn new(timeout: Duration) -> Self {
+        Self { timeout }
+    }
+
+    fn read_https_response(stream: &mut dyn Read) -> io::Result<Vec<u8>> {
+        let mut response = vec![];
+        let mut buf = [0u8; 4096];
+        loop {
+            // Plenty of servers close the connection without a TLS close_notify, so an error here
+            // just means we have all we're going to get.
+            let at_eof = match stream.read(&mut buf) {
+                Ok(0) | Err(_) => true,
+                Ok(len) => {
+                    response.extend_from_slice(&buf[..len]);
+                    false
+                }
+            };
+            match https_response_body(&response, at_eof) {
+                Ok(Some(body)) => return Ok(body),
+                Ok(None) if response.len() < MAX_HTTPS_RESPONSE_LEN => (),
+                Ok(None) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "HTTPS response too long",
+                    ))
+                }
+                Err(msg) => return Err(io::Error::new(io::ErrorKind::InvalidData, msg)),
+            }
+        }
+    }
+}
+
+type LookupJob = (String, oneshot::Sender<Result<LookupIp, ResolveError>>);
+
+pub struct SecureResolverWrapper {
+    inner: Arc<SecureResolverInner>,
+    job_tx: SyncSender<LookupJob>,
+}
+
+impl ResolverWrapper for SecureResolverWrapper {
+    fn lookup_ip(&self, host: &str) -> Box<WrappedLookupIpFuture> {
+        if let Some(lookup_ip) = self.inner.cached(host) {
+            return Box::new(future::ok(lookup_ip));
+        }
+        let (tx, rx) = oneshot::channel();
+        match self.job_tx.try_send((host.to_string(), tx)) {
+            Ok(()) => (),
+            Err(TrySendError::Full(_)) => {
+                warning!(
+                    self.inner.logger,
+                    "Too many secure DNS lookups waiting; failing lookup of {}",
+                    host
+                );
+                return Box::new(future::err(ResolveError::from(
+                    "Too many secure DNS lookups waiting",
+                )));
+            }
+            Err(TrySendError::Disconnected(_)) => {
+                return Box::new(future::err(ResolveError::from(
+                    "Secure DNS lookup threads are gone",
+                )))
+            }
+        }
+        Box::new(rx.then(|result| match result {
+            Ok(lookup_result) => lookup_result,
+            Err(_) => Err(ResolveError::from("Secure DNS lookup was abandoned")),
+        }))
+    }
+}
+
+impl SecureResolverWrapper {
+    pub fn new(
+        servers: Vec<SecureDnsServer>,
+        transport: Box<dyn SecureDnsTransport>,
+        threads: usize,
+    ) -> Self {
+        Self::with_pool(servers, transport, threads, MAX_WAITING_LOOKUPS)
+    }
+
+    // The transports block, so lookups go to a few threads of their own rather than holding up the
+    // reactor; once max_waiting lookups are queued for them, further lookups fail right away.
+    fn with_pool(
+        servers: Vec<SecureDnsServer>,
+        transport: Box<dyn SecureDnsTransport>,
+        threads: usize,
+        max_waiting: usize,
+    ) -> Self {
+        let inner = Arc::new(SecureResolverInner {
+            servers,
+            transport,
+            cache: Mutex::new(HashMap::new()),
+            logger: Logger::new("SecureResolver"),
+        });
+        let (job_tx, job_rx) = mpsc::sync_channel(max_waiting);
+        let job_rx = Arc::new(Mutex::new(job_rx));
+        (0..threads).for_each(|_| {
+            let inner = inner.clone();
+            let job_rx = job_rx.clone();
+            thread::spawn(move || Self::run_lookups(inner, job_rx));
+        });
+        Self { inner, job_tx }
+    }
+
+    // Ends when the wrapper, and with it the sending end of the queue, is dropped
+    fn run_lookups(inner: Arc<SecureResolverInner>, job_rx: Arc<Mutex<Receiver<LookupJob>>>) {
+        loop {
+            let job = job_rx
+                .lock()
+                .expect("Secure DNS lookup queue is poisoned")
+                .recv();
+            match job {
+                Ok((host, tx)) => {
+                    let _ = tx.send(inner.resolve(&host));
+                }
+                Err(_) => break,
+            }
+        }
+    }
+}
+
+struct SecureResolverInner {
+    servers: Vec<SecureDnsServer>,
+    transport: Box<dyn SecureDnsTransport>,
+    cache: Mutex<HashMap<String, LookupIp>>,
+    logger: Logger,
+}
+
+impl SecureResolverInner {
+    fn resolve(&self, host: &str) -> Result<LookupIp, ResolveError> {
+        let name = Name::from_str(host)?;
+        for server in &self.servers {
+            match self.query_server(server, &name) {
+                Ok(Some(lookup_ip)) => {
+                    self.cache(host, &lookup_ip);
+                    return Ok(lookup_ip);
+                }
+                Ok(None) => {
+                    return Err(ResolveErrorKind::NoRecordsFound {
+                        query: Query::query(name, RecordType::A),
+                        valid_until: None,
+                    }
+                    .into())
+                }
+                Err(msg) => warning!(
+                    self.logger,
+                    "Secure DNS server {} failed to resolve {}: {}",
+                    server,
+                    host,
+                    msg
+                ),
+            }
+        }
+        Err(ResolveErrorKind::Msg(format!("No secure DNS server could resolve {}", host)).into())
+    }
+
+    // Ok(None) means the server answered, but there are no addresses to be had
+    fn query_server(
+        &self,
+        server: &SecureDnsServer,
+        name: &Name,
+    ) -> Result<Option<LookupIp>, String> {
+        for record_type in &[RecordType::A, RecordType::AAAA] {
+            let query = Query::query(name.clone(), *record_type);
+            let request = make_request(deterministic::random_u16("DNS query id"), query.clone());
+            let request_bytes = request
+                .to_vec()
+                .map_err(|e| format!("could not serialize query: {}", e))?;
+            let response_bytes = self
+                .transport
+                .exchange(server, &request_bytes)
+                .map_err(|e| format!("{}", e))?;
+            let response = Message::from_vec(&response_bytes)
+                .map_err(|e| format!("unparseable response: {}", e))?;
+            if response.id() != request.id() {
+                return Err(format!(
+                    "response ID {} doesn't match query ID {}",
+                    response.id(),
+                    request.id()
+                ));
+            }
+            match response.response_code() {
+                ResponseCode::NoError => (),
+                ResponseCode::NXDomain => return Ok(None),
+                code => return Err(format!("server answered {}", code)),
+            }
+            let records = response
+                .answers()
+                .iter()
+                .filter(|record| matches!(record.rdata(), RData::A(_) | RData::AAAA(_)))
+                .cloned()
+                .collect::<Vec<Record>>();
+            if let Some(ttl) = records.iter().map(|record| record.ttl()).min() {
+                let valid_until =
+                    Instant::now() + Duration::from_secs(u64::from(ttl)).min(MAX_CACHE_TTL);
+                return Ok(Some(LookupIp::from(Lookup::new_with_deadline(
+                    query,
+                    Arc::new(records),
+                    valid_until,
+                ))));
+            }
+        }
+        Ok(None)
+    }
+
+    fn cached(&self, host: &str) -> Option<LookupIp> {
+        let key = host.to_lowercase();
+        let mut cache = self.cache.lock().expect("Secure DNS cache is poisoned");
+        match cache.get(&key) {
+            Some(lookup_ip) if lookup_ip.valid_until() > Instant::now() => Some(lookup_ip.clone()),
+            Some(_) => {
+                cache.remove(&key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn cache(&self, host: &str, lookup_ip: &LookupIp) {
+        let now = Instant::now();
+        if lookup_ip.valid_until() <= now {
+            return;
+        }
+        let mut cache = self.cache.lock().expect("Secure DNS cache is poisoned");
+        if cache.len() >= MAX_CACHE_ENTRIES {
+            cache.retain(|_, cached| cached.valid_until() > now);
+            if cache.len() >= MAX_CACHE_ENTRIES {
+                cache.clear();
+            }
+        }
+        cache.insert(host.to_lowercase(), lookup_ip.clone());
+    }
+}
+
+fn make_request(id: u16, query: Query) -> Message {
+    let mut request = Message::new();
+    request
+        .set_id(id)
+        .set_message_type(MessageType::Query)
+        .set_op_code(OpCode::Query)
+        .set_recursion_desired(true)
+        .add_query(query);
+    request
+}
+
+fn tls_frame(request: &[u8]) -> Vec<u8> {
+    let mut frame = (request.len() as u16).to_be_bytes().to_vec();
+    frame.extend_from_slice(request);
+    frame
+}
+
+fn https_request(server: &SecureDnsServer, request: &[u8]) -> Vec<u8> {
+    let host = if server.host.contains(':') {
+        format!("[{}]", server.host)
+    } else {
+        server.host.clone()
+    };
+    let authority = if server.port == 443 {
+        host
+    } else {
+        format!("{}:{}", host, server.port)
+    };
+    let mut http_request = format!(
+        "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/dns-message\r\n\
+         Accept: application/dns-message\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        server.path,
+        authority,
+        request.len()
+    )
+    .into_bytes();
+    http_request.extend_from_slice(request);
+    http_request
+}
+
+// Ok(None) means the response isn't complete yet
+pub fn https_response_body(response: &[u8], at_eof: bool) -> Result<Option<Vec<u8>>, String> {
+    let header_end = match find(response, b"\r\n\r\n") {
+        Some(idx) => idx,
+        None if at_eof => return Err("HTTPS response ended in its headers".to_string()),
+        None => return Ok(None),
+    };
+    let head = String::from_utf8_lossy(&response[..header_end]).to_string();
+    let mut lines = head.split("\r\n");
+    let status_line = lines.next().unwrap_or("");
+    match status_line.split(' ').nth(1) {
+        Some("200") => (),
+        _ => return Err(format!("HTTPS server responded '{}'", status_line)),
+    }
+    let headers = lines
+        .filter_map(|line| {
+            let idx = line.find(':')?;
+            Some((
+                line[..idx].trim().to_lowercase(),
+                line[(idx + 1)..].trim().to_lowercase(),
+            ))
+        })
+        .collect::<HashMap<String, String>>();
+    let body = &response[(header_end + 4)..];
+    if headers.get("transfer-encoding").map(|value| value.as_str()) == Some("chunked") {
+        return match dechunk(body)? {
+            Some(body) => Ok(Some(body)),
+            None if at_eof => Err("HTTPS response ended in its body".to_string()),
+            None => Ok(None),
+        };
+    }
+    match headers.get("content-length") {
+        Some(value) => {
+            let length = usize::from_str(value)
+                .map_err(|_| format!("HTTPS response has bad Content-Length '{}'", value))?;
+            if body.len() >= length {
+                Ok(Some(body[..length].to_vec()))
+            } else if at_eof {
+                Err("HTTPS response ended in its body".to_string())
+            } else {
+                Ok(None)
+            }
+        }
+        None if at_eof => Ok(Some(body.to_vec())),
+        None => Ok(None),
+    }
+}
+
+fn dechunk(mut chunked: &[u8]) -> Result<Option<Vec<u8>>, String> {
+    let mut body = vec![];
+    loop {
+        let line_end = match find(chunked, b"\r\n") {
+            Some(idx) => idx,
+            None => return Ok(None),
+        };
+        let size_str = String::from_utf8_lossy(&chunked[..line_end]).to_string();
+        let size_str = size_str.split(';').next().unwrap_or("").trim();
+        let size = usize::from_str_radix(size_str, 16)
+            .map_err(|_| format!("HTTPS response has bad chunk size '{}'", size_str))?;
+        if size == 0 {
+            return Ok(Some(body));
+        }
+        let chunk_start = line_end + 2;
+        if chunked.len() < chunk_start + size + 2 {
+            return Ok(None);
+        }
+        body.extend_from_slice(&chunked[chunk_start..(chunk_start + size)]);
+        chunked = &chunked[(chunk_start + size + 2)..];
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::logging::init_test_logging;
+    use crate::test_utils::logging::TestLogHandler;
+    use std::net::IpAddr;
+
+    enum Answer {
+        Addresses(Vec<IpAddr>, u32),
+        Code(ResponseCode),
+        WrongId,
+        Failure(&'static str),
+    }
+
+    struct SecureDnsTransportMock {
+        exchange_params: Arc<Mutex<Vec<(SecureDnsServer, Message)>>>,
+        exchange_answers: Mutex<Vec<Answer>>,
+    }
+
+    impl SecureDnsTransport for SecureDnsTransportMock {
+        fn exchange(&self, server: &SecureDnsServer, request: &[u8]) -> io::Result<Vec<u8>> {
+            let request = Message::from_vec(request).unwrap();
+            self.exchange_params
+                .lock()
+                .unwrap()
+                .push((server.clone(), request.clone()));
+            let mut response = Message::new();
+            response
+                .set_id(request.id())
+                .set_message_type(MessageType::Response)
+                .add_queries(request.queries().to_vec());
+            match self.exchange_answers.lock().unwrap().remove(0) {
+                Answer::Addresses(ip_addrs, ttl) => {
+                    let name = request.queries()[0].name().clone();
+                    ip_addrs.into_iter().for_each(|ip_addr| {
+                        let rdata = match ip_addr {
+                            IpAddr::V4(ip_addr) => RData::A(ip_addr),
+                            IpAddr::V6(ip_addr) => RData::AAAA(ip_addr),
+                        };
+                        response.add_answer(Record::from_rdata(name.clone(), ttl, rdata));
+                    });
+                }
+                Answer::Code(code) => {
+                    response.set_response_code(code);
+                }
+                Answer::WrongId => {
+                    response.set_id(request.id().wrapping_add(1));
+                }
+                Answer::Failure(msg) => return Err(io::Error::new(io::ErrorKind::Other, msg)),
+            }
+            Ok(response.to_vec().unwrap())
+        }
+    }
+
+    impl SecureDnsTransportMock {
+        fn new() -> Self {
+            Self {
+                exchange_params: Arc::new(Mutex::new(vec![])),
+                exchange_answers: Mutex::new(vec![]),
+            }
+        }
+
+        fn exchange_params(mut self, params: &Arc<Mutex<Vec<(SecureDnsServer, Message)>>>) -> Self {
+            self.exchange_params = params.clone();
+            self
+        }
+
+        fn exchange_answer(self, answer: Answer) -> Self {
+            self.exchange_answers.lock().unwrap().push(answer);
+            self
+        }
+    }
+
+    fn servers() -> Vec<SecureDnsServer> {
+        vec![
+            SecureDnsServer::from_str("https://dns.first.com").unwrap(),
+            SecureDnsServer::from_str("tls://2.2.2.2").unwrap(),
+        ]
+    }
+
+    fn ip(s: &str) -> IpAddr {
+        IpAddr::from_str(s).unwrap()
+    }
+
+    fn addresses(lookup_ip: &LookupIp) -> Vec<IpAddr> {
+        lookup_ip.iter().collect()
+    }
+
+    fn queries(params: &[(SecureDnsServer, Message)]) -> Vec<(String, RecordType)> {
+        params
+            .iter()
+            .map(|(server, request)| (server.to_string(), request.queries()[0].query_type()))
+            .collect()
+    }
+
+    #[test]
+    fn resolves_through_first_server() {
+        let exchange_params_arc = Arc::new(Mutex::new(vec![]));
+        let transport = SecureDnsTransportMock::new()
+            .exchange_params(&exchange_params_arc)
+            .exchange_answer(Answer::Addresses(vec![ip("1.2.3.4"), ip("2.3.4.5")], 300));
+        let subject = SecureResolverWrapper::new(servers(), Box::new(transport), 1);
+
+        let result = subject.lookup_ip("booga.com.").wait().unwrap();
+
+        assert_eq!(addresses(&result), vec![ip("1.2.3.4"), ip("2.3.4.5")]);
+        let exchange_params = exchange_params_arc.lock().unwrap();
+        assert_eq!(
+            queries(&exchange_params),
+            vec![(
+                "https://dns.first.com:443/dns-query".to_string(),
+                RecordType::A
+            )]
+        );
+        let request = &exchange_params[0].1;
+        assert_eq!(
+            request.queries()[0].name(),
+            &Name::from_str("booga.com.").unwrap()
+        );
+        assert_eq!(request.message_type(), MessageType::Query);
+        assert_eq!(request.recursion_desired(), true);
+    }
+
+    #[test]
+    fn asks_for_ipv6_addresses_if_there_are_no_ipv4_addresses() {
+        let exchange_params_arc = Arc::new(Mutex::new(vec![]));
+        let transport = SecureDnsTransportMock::new()
+            .exchange_params(&exchange_params_arc)
+            .exchange_answer(Answer::Addresses(vec![], 300))
+            .exchange_answer(Answer::Addresses(vec![ip("2001:db8::1")], 300));
+        let subject = SecureResolverWrapper::new(servers(), Box::new(transport), 1);
+
+        let result = subject.inner.resolve("booga.com.").unwrap();
+
+        assert_eq!(addresses(&result), vec![ip("2001:db8::1")]);
+        assert_eq!(
+            queries(&exchange_params_arc.lock().unwrap()),
+            vec![
+                (
+                    "https://dns.first.com:443/dns-query".to_string(),
+                    RecordType::A
+                ),
+                (
+                    "https://dns.first.com:443/dns-query".to_string(),
+                    RecordType::AAAA
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn falls_back_to_next_server_when_one_fails() {
+        init_test_logging();
+        let exchange_params_arc = Arc::new(Mutex::new(vec![]));
+        let transport = SecureDnsTransportMock::new()
+            .exchange_params(&exchange_params_arc)
+            .exchange_answer(Answer::Failure("Connection refused"))
+            .exchange_answer(Answer::Addresses(vec![ip("3.4.5.6")], 300));
+        let subject = SecureResolverWrapper::new(servers(), Box::new(transport), 1);
+
+        let result = subject.inner.resolve("fallback.com.").unwrap();
+
+        assert_eq!(addresses(&result), vec![ip("3.4.5.6")]);
+        assert_eq!(
+            queries(&exchange_params_arc.lock().unwrap()),
+            vec![
+                (
+                    "https://dns.first.com:443/dns-query".to_string(),
+                    RecordType::A
+                ),
+                ("tls://2.2.2.2:853".to_string(), RecordType::A),
+            ]
+        );
+        TestLogHandler::new().exists_log_containing("WARN: SecureResolver: Secure DNS server https://dns.first.com:443/dns-query failed to resolve fallback.com.: Connection refused");
+    }
+
+    #[test]
+    fn mismatched_and_failed_responses_are_treated_as_server_failures() {
+        let transport = SecureDnsTransportMock::new()
+            .exchange_answer(Answer::WrongId)
+            .exchange_answer(Answer::Code(ResponseCode::ServFail));
+        let subject = SecureResolverWrapper::new(servers(), Box::new(transport), 1);
+
+        let result = subject.inner.resolve("nobody.com.");
+
+        assert_eq!(
+            format!("{}", result.err().unwrap()),
+            "No secure DNS server could resolve nobody.com.".to_string()
+        );
+    }
+
+    #[test]
+    fn nonexistent_domain_is_not_retried_on_other_servers() {
+        let exchange_params_arc = Arc::new(Mutex::new(vec![]));
+        let transport = SecureDnsTransportMock::new()
+            .exchange_params(&exchange_params_arc)
+            .exchange_answer(Answer::Code(ResponseCode::NXDomain));
+        let subject = SecureResolverWrapper::new(servers(), Box::new(transport), 1);
+
+        let result = subject.lookup_ip("nonexistent.com.").wait();
+
+        match result.err().unwrap().kind() {
+            ResolveErrorKind::NoRecordsFound { query, .. } => {
+                assert_eq!(query.name(), &Name::from_str("nonexistent.com.").unwrap())
+            }
+            kind => panic!("Expected NoRecordsFound, got {:?}", kind),
+        }
+        assert_eq!(exchange_params_arc.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn answers_are_cached_until_they_expire() {
+        let exchange_params_arc = Arc::new(Mutex::new(vec![]));
+        let transport = SecureDnsTransportMock::new()
+            .exchange_params(&exchange_params_arc)
+            .exchange_answer(Answer::Addresses(vec![ip("1.2.3.4")], 300))
+            .exchange_answer(Answer::Addresses(vec![ip("5.6.7.8")], 0))
+            .exchange_answer(Answer::Addresses(vec![ip("6.7.8.9")], 0));
+        let subject = SecureResolverWrapper::new(servers(), Box::new(transport), 1);
+
+        let first = subject.lookup_ip("cached.com.").wait().unwrap();
+        let second = subject.lookup_ip("CACHED.com.").wait().unwrap();
+        let third = subject.lookup_ip("uncached.com.").wait().unwrap();
+        let fourth = subject.lookup_ip("uncached.com.").wait().unwrap();
+
+        assert_eq!(addresses(&first), vec![ip("1.2.3.4")]);
+        assert_eq!(addresses(&second), vec![ip("1.2.3.4")]);
+        assert_eq!(addresses(&third), vec![ip("5.6.7.8")]);
+        assert_eq!(addresses(&fourth), vec![ip("6.7.8.9")]);
+        assert_eq!(exchange_params_arc.lock().unwrap().len(), 3);
+    }
+
+    struct GatedTransport {
+        entered_tx: Mutex<mpsc::Sender<()>>,
+        gate_rx: Mutex<mpsc::Receiver<()>>,
+    }
+
+    impl SecureDnsTransport for GatedTransport {
+        fn exchange(&self, _server: &SecureDnsServer, _request: &[u8]) -> io::Result<Vec<u8>> {
+            let _ = self.entered_tx.lock().unwrap().send(());
+            let _ = self.gate_rx.lock().unwrap().recv();
+            Err(io::Error::new(io::ErrorKind::Other, "gate closed"))
+        }
+    }
+
+    #[test]
+    fn lookups_fail_right_away_once_the_queue_is_full() {
+        let (entered_tx, entered_rx) = mpsc::channel();
+        let (gate_tx, gate_rx) = mpsc::channel::<()>();
+        let transport = GatedTransport {
+            entered_tx: Mutex::new(entered_tx),
+            gate_rx: Mutex::new(gate_rx),
+        };
+        let subject = SecureResolverWrapper::with_pool(servers(), Box::new(transport), 1, 1);
+        let busy = subject.lookup_ip("busy.com.");
+        entered_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        let waiting = subject.lookup_ip("waiting.com.");
+
+        let refused = subject.lookup_ip("refused.com.").wait();
+
+        drop(gate_tx);
+        assert_eq!(
+            format!("{}", refused.err().unwrap()),
+            "Too many secure DNS lookups waiting".to_string()
+        );
+        assert_eq!(
+            format!("{}", busy.wait().err().unwrap()),
+            "No secure DNS server could resolve busy.com.".to_string()
+        );
+        assert_eq!(
+            format!("{}", waiting.wait().err().unwrap()),
+            "No secure DNS server could resolve waiting.com.".to_string()
+        );
+    }
+
+    #[test]
+    fn lookups_run_on_as_many_threads_as_asked_for() {
+        let (entered_tx, entered_rx) = mpsc::channel();
+        let (gate_tx, gate_rx) = mpsc::channel::<()>();
+        let transport = GatedTransport {
+            entered_tx: Mutex::new(entered_tx),
+            gate_rx: Mutex::new(gate_rx),
+        };
+        let subject = SecureResolverWrapper::new(servers(), Box::new(transport), 3);
+
+        let lookups = (0..4)
+            .map(|n| subject.lookup_ip(&format!("host{}.com.", n)))
+            .collect::<Vec<_>>();
+
+        (0..3).for_each(|_| entered_rx.recv_timeout(Duration::from_secs(5)).unwrap());
+        assert!(entered_rx.recv_timeout(Duration::from_millis(100)).is_err());
+        drop(gate_tx);
+        lookups.into_iter().for_each(|lookup| {
+            let _ = lookup.wait();
+        });
+    }
+
+    #[test]
+    fn tls_requests_are_length_prefixed() {
+        let result = tls_frame(&[1, 2, 3]);
+
+        assert_eq!(result, vec![0, 3, 1, 2, 3]);
+    }
+
+    #[test]
+    fn https_requests_are_posted_to_the_server_path() {
+        let server = SecureDnsServer::from_str("https://[2001:db8::1]:8443/resolve").unwrap();
+
+        let result = https_request(&server, &[1, 2, 3]);
+
+        let mut expected = b"POST /resolve HTTP/1.1\r\nHost: [2001:db8::1]:8443\r\n\
+            Content-Type: application/dns-message\r\nAccept: application/dns-message\r\n\
+            Content-Length: 3\r\nConnection: close\r\n\r\n"
+            .to_vec();
+        expected.extend_from_slice(&[1, 2, 3]);
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn https_response_body_handles_content_length() {
+        let response = b"HTTP/1.1 200 OK\r\nContent-Length: 4\r\n\r\nbooga";
+
+        assert_eq!(https_response_body(&response[..40], false), Ok(None));
+        assert_eq!(
+            https_response_body(&response[..40], true),
+            Err("HTTPS response ended in its body".to_string())
+        );
+        assert_eq!(
+            https_response_body(response, false),
+            Ok(Some(b"boog".to_vec()))
+        );
+    }
+
+    #[test]
+    fn https_response_body_handles_chunks() {
+        let response =
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: Chunked\r\n\r\n3\r\nboo\r\n2;ext\r\nga\r\n0\r\n\r\n";
+
+        assert_eq!(https_response_body(&response[..60], false), Ok(None));
+        assert_eq!(
+            https_response_body(response, false),
+            Ok(Some(b"booga".to_vec()))
+        );
+    }
+
+    #[test]
+    fn https_response_body_without_length_ends_at_eof() {
+        let response = b"HTTP/1.0 200 OK\r\nContent-Type: application/dns-message\r\n\r\nbooga";
+
+        assert_eq!(https_response_body(response, false), Ok(None));
+        assert_eq!(
+            https_response_body(response, true),
+            Ok(Some(b"booga".to_vec()))
+        );
+    }
+
+    #[test]
+    fn https_response_body_rejects_failure_statuses() {
+        let response = b"HTTP/1.1 415 Unsupported Media Type\r\nContent-Length: 0\r\n\r\n";
+
+        assert_eq!(
+            https_response_body(response, false),
+            Err("HTTPS server responded 'HTTP/1.1 415 Unsupported Media Type'".to_string())
+        );
+    }
+}
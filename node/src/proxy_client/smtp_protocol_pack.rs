@@ -0,0 +1,87 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use crate::proxy_client::protocol_pack::{
+    ExitProtocolPack, Passthrough, RequestFramer, ResponseHandler,
+};
+use crate::sub_lib::proxy_server::ProxyProtocol;
+
+// Mail submission: the server greets the client before the client says anything, and every
+// command and message line must end in CRLF
+pub struct SmtpExitProtocolPack {}
+
+impl ExitProtocolPack for SmtpExitProtocolPack {
+    fn proxy_protocol(&self) -> ProxyProtocol {
+        ProxyProtocol::SMTP
+    }
+
+    fn server_speaks_first(&self) -> bool {
+        true
+    }
+
+    fn request_framer(&self) -> Box<dyn RequestFramer> {
+        Box::new(SmtpRequestFramer::default())
+    }
+
+    fn response_handler(&self) -> Box<dyn ResponseHandler> {
+        Box::new(Passthrough {})
+    }
+}
+
+// Submission servers may reject bare LFs, so they become CRLFs; a CR at the end of one packet
+// pairs with an LF at the start of the next
+#[derive(Default)]
+pub struct SmtpRequestFramer {
+    last_was_cr: bool,
+}
+
+impl RequestFramer for SmtpRequestFramer {
+    fn frame(&mut self, data: Vec<u8>) -> Vec<u8> {
+        let mut framed = Vec::with_capacity(data.len());
+        data.into_iter().for_each(|byte| {
+            if byte == b'\n' && !self.last_was_cr {
+                framed.push(b'\r');
+            }
+            self.last_was_cr = byte == b'\r';
+            framed.push(byte);
+        });
+        framed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn smtp_pack_opens_streams_before_the_client_speaks() {
+        let subject = SmtpExitProtocolPack {};
+
+        assert_eq!(subject.proxy_protocol(), ProxyProtocol::SMTP);
+        assert_eq!(subject.server_speaks_first(), true);
+        assert_eq!(
+            subject
+                .response_handler()
+                .handle(b"220 smtp.example.com ESMTP\r\n".to_vec()),
+            b"220 smtp.example.com ESMTP\r\n".to_vec()
+        );
+    }
+
+    #[test]
+    fn framer_turns_bare_line_feeds_into_crlfs() {
+        let mut subject = SmtpRequestFramer::default();
+
+        let result = subject.frame(b"EHLO client\nMAIL FROM:<a@b.c>\r\n\n".to_vec());
+
+        assert_eq!(result, b"EHLO client\r\nMAIL FROM:<a@b.c>\r\n\r\n".to_vec());
+    }
+
+    #[test]
+    fn framer_remembers_a_carriage_return_at_the_end_of_a_packet() {
+        let mut subject = SmtpRequestFramer::default();
+
+        let first = subject.frame(b"QUIT\r".to_vec());
+        let second = subject.frame(b"\nRSET\n".to_vec());
+
+        assert_eq!(first, b"QUIT\r".to_vec());
+        assert_eq!(second, b"\nRSET\r\n".to_vec());
+    }
+}
@@ -1,6 +1,7 @@
 // Copyright (c) 2017-2019, Substratum LLC (https://substratum.net) and/or its affiliates. All rights reserved.
 
-use crate::proxy_client::stream_reader::StreamReader;
+use crate::proxy_client::protocol_pack::ProtocolPackRegistry;
+use crate::proxy_client::stream_reader::{ReadGates, StreamReader};
 use crate::proxy_client::stream_writer::StreamWriter;
 use crate::sub_lib::channel_wrappers::FuturesChannelFactory;
 use crate::sub_lib::channel_wrappers::FuturesChannelFactoryReal;
@@ -19,15 +20,20 @@ use std::io;
 use std::net::IpAddr;
 use std::net::SocketAddr;
 use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 pub struct StreamEstablisher {
     pub cryptde: &'static dyn CryptDE,
     pub stream_adder_tx: Sender<(StreamKey, Box<dyn SenderWrapper<SequencedPacket>>)>,
-    pub stream_killer_tx: Sender<(StreamKey, u64)>,
+    pub stream_killer_tx: Sender<StreamKey>,
     pub stream_connector: Box<dyn StreamConnector>,
     pub proxy_client_sub: Recipient<InboundServerData>,
     pub logger: Logger,
     pub channel_factory: Box<dyn FuturesChannelFactory<SequencedPacket>>,
+    pub stream_idle_timeout_opt: Option<Duration>,
+    pub protocol_packs: Arc<ProtocolPackRegistry>,
+    pub read_gates: ReadGates,
 }
 
 impl Clone for StreamEstablisher {
@@ -40,6 +46,9 @@ impl Clone for StreamEstablisher {
             proxy_client_sub: self.proxy_client_sub.clone(),
             logger: self.logger.clone(),
             channel_factory: Box::new(FuturesChannelFactoryReal {}),
+            stream_idle_timeout_opt: self.stream_idle_timeout_opt,
+            protocol_packs: self.protocol_packs.clone(),
+            read_gates: self.read_gates.clone(),
         }
     }
 }
@@ -58,10 +67,12 @@ impl StreamEstablisher {
             &self.logger,
         )?;
 
+        let last_activity = Arc::new(Mutex::new(Instant::now()));
         self.spawn_stream_reader(
             &payload.clone(),
             connection_info.reader,
             connection_info.peer_addr,
+            last_activity.clone(),
         )?;
 
         let (tx_to_write, rx_to_write) = self.channel_factory.make(connection_info.peer_addr);
@@ -70,6 +81,10 @@ impl StreamEstablisher {
             connection_info.peer_addr,
             rx_to_write,
             payload.stream_key,
+            last_activity,
+            self.protocol_packs
+                .pack_for(payload.protocol)
+                .request_framer(),
         );
         tokio::spawn(stream_writer);
 
@@ -84,6 +99,7 @@ impl StreamEstablisher {
         payload: &ClientRequestPayload_0v1,
         read_stream: Box<dyn ReadHalfWrapper>,
         peer_addr: SocketAddr,
+        last_activity: Arc<Mutex<Instant>>,
     ) -> io::Result<()> {
         let stream_reader = StreamReader::new(
             payload.stream_key,
@@ -91,6 +107,12 @@ impl StreamEstablisher {
             read_stream,
             self.stream_killer_tx.clone(),
             peer_addr,
+            last_activity,
+            self.stream_idle_timeout_opt,
+            self.protocol_packs
+                .pack_for(payload.protocol)
+                .response_handler(),
+            self.read_gates.open(payload.stream_key),
         );
         debug!(self.logger, "Spawning StreamReader for {}", peer_addr);
         tokio::spawn(stream_reader);
@@ -105,9 +127,12 @@ pub trait StreamEstablisherFactory: Send {
 pub struct StreamEstablisherFactoryReal {
     pub cryptde: &'static dyn CryptDE,
     pub stream_adder_tx: Sender<(StreamKey, Box<dyn SenderWrapper<SequencedPacket>>)>,
-    pub stream_killer_tx: Sender<(StreamKey, u64)>,
+    pub stream_killer_tx: Sender<StreamKey>,
     pub proxy_client_subs: ProxyClientSubs,
     pub logger: Logger,
+    pub stream_idle_timeout_opt: Option<Duration>,
+    pub protocol_packs: Arc<ProtocolPackRegistry>,
+    pub read_gates: ReadGates,
 }
 
 impl StreamEstablisherFactory for StreamEstablisherFactoryReal {
@@ -120,6 +145,9 @@ impl StreamEstablisherFactory for StreamEstablisherFactoryReal {
             proxy_client_sub: self.proxy_client_subs.inbound_server_data.clone(),
             logger: self.logger.clone(),
             channel_factory: Box::new(FuturesChannelFactoryReal {}),
+            stream_idle_timeout_opt: self.stream_idle_timeout_opt,
+            protocol_packs: self.protocol_packs.clone(),
+            read_gates: self.read_gates.clone(),
         }
     }
 }
@@ -177,6 +205,9 @@ mod tests {
                 proxy_client_sub,
                 logger: Logger::new("ProxyClient"),
                 channel_factory: Box::new(FuturesChannelFactoryReal {}),
+                stream_idle_timeout_opt: None,
+                protocol_packs: Arc::new(ProtocolPackRegistry::new()),
+                read_gates: ReadGates::new(),
             };
             subject
                 .spawn_stream_reader(
@@ -191,9 +222,11 @@ mod tests {
                         target_port: 0,
                         protocol: ProxyProtocol::HTTP,
                         originator_public_key: subject.cryptde.public_key().clone(),
+                        is_resumption: false,
                     },
                     read_stream,
                     SocketAddr::from_str("1.2.3.4:5678").unwrap(),
+                    Arc::new(Mutex::new(Instant::now())),
                 )
                 .expect("spawn_stream_reader () failed");
 
@@ -1,16 +1,20 @@
 // Copyright (c) 2017-2019, Substratum LLC (https://substratum.net) and/or its affiliates. All rights reserved.
 #![allow(proc_macro_derive_resolution_fallback)]
 
+use crate::proxy_client::protocol_pack::ProtocolPackRegistry;
 use crate::proxy_client::resolver_wrapper::ResolverWrapper;
 use crate::proxy_client::stream_establisher::StreamEstablisherFactoryReal;
 use crate::proxy_client::stream_establisher::{StreamEstablisher, StreamEstablisherFactory};
+use crate::proxy_client::stream_reader::ReadGates;
 use crate::sub_lib::accountant::ReportExitServiceProvidedMessage;
 use crate::sub_lib::channel_wrappers::SenderWrapper;
 use crate::sub_lib::cryptde::CryptDE;
 use crate::sub_lib::logger::Logger;
 use crate::sub_lib::proxy_client::{error_socket_addr, ProxyClientSubs};
-use crate::sub_lib::proxy_client::{DnsResolveFailure_0v1, InboundServerData};
-use crate::sub_lib::proxy_server::ClientRequestPayload_0v1;
+use crate::sub_lib::proxy_client::{
+    DnsResolveFailure_0v1, InboundServerData, InboundServerDatagram, UDP_IDLE_TIMEOUT,
+};
+use crate::sub_lib::proxy_server::{ClientRequestPayload_0v1, ClientUdpRequestPayload_0v1};
 use crate::sub_lib::sequence_buffer::SequencedPacket;
 use crate::sub_lib::stream_key::StreamKey;
 use crate::sub_lib::wallet::Wallet;
@@ -19,25 +23,35 @@ use futures::future;
 use futures::future::Future;
 use std::collections::HashMap;
 use std::io;
-use std::net::{AddrParseError, IpAddr, SocketAddr};
+use std::net::{AddrParseError, IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
 use std::str::FromStr;
 use std::sync::mpsc;
-use std::sync::mpsc::Receiver;
+use std::sync::mpsc::{Receiver, Sender};
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
 use tokio::prelude::future::FutureResult;
 use tokio::prelude::future::{err, ok};
 use trust_dns_resolver::error::ResolveError;
 use trust_dns_resolver::lookup_ip::LookupIp;
 
+const MAX_DATAGRAM_SIZE: usize = 65536;
+
 pub trait StreamHandlerPool {
     fn process_package(&self, payload: ClientRequestPayload_0v1, paying_wallet: Option<Wallet>);
+    fn process_udp_package(
+        &self,
+        payload: ClientUdpRequestPayload_0v1,
+        paying_wallet: Option<Wallet>,
+    );
 }
 
 pub struct StreamHandlerPoolReal {
     inner: Arc<Mutex<StreamHandlerPoolRealInner>>,
     stream_adder_rx: Receiver<(StreamKey, Box<dyn SenderWrapper<SequencedPacket>>)>,
-    stream_killer_rx: Receiver<(StreamKey, u64)>,
+    stream_killer_rx: Receiver<StreamKey>,
+    udp_socket_killer_rx: Receiver<StreamKey>,
 }
 
 struct StreamHandlerPoolRealInner {
@@ -49,6 +63,11 @@ struct StreamHandlerPoolRealInner {
     establisher_factory: Box<dyn StreamEstablisherFactory>,
     exit_service_rate: u64,
     exit_byte_rate: u64,
+    // One socket per stream key; each has a thread reading responses until it goes idle
+    udp_sockets: HashMap<StreamKey, UdpSocket>,
+    udp_socket_killer_tx: Sender<StreamKey>,
+    udp_idle_timeout: Duration,
+    protocol_packs: Arc<ProtocolPackRegistry>,
 }
 
 impl StreamHandlerPool for StreamHandlerPoolReal {
@@ -56,12 +75,22 @@ impl StreamHandlerPool for StreamHandlerPoolReal {
         self.do_housekeeping();
         Self::process_package(payload, paying_wallet, self.inner.clone())
     }
+
+    fn process_udp_package(
+        &self,
+        payload: ClientUdpRequestPayload_0v1,
+        paying_wallet: Option<Wallet>,
+    ) {
+        self.do_housekeeping();
+        Self::process_udp_package(payload, paying_wallet, self.inner.clone())
+    }
 }
 
 type StreamEstablisherResult =
     Box<dyn Future<Item = Box<dyn SenderWrapper<SequencedPacket> + 'static>, Error = String>>;
 
 impl StreamHandlerPoolReal {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         resolver: Box<dyn ResolverWrapper>,
         cryptde: &'static dyn CryptDE,
@@ -69,9 +98,13 @@ impl StreamHandlerPoolReal {
         proxy_client_subs: ProxyClientSubs,
         exit_service_rate: u64,
         exit_byte_rate: u64,
+        stream_idle_timeout_opt: Option<Duration>,
+        protocol_packs: Arc<ProtocolPackRegistry>,
+        read_gates: ReadGates,
     ) -> StreamHandlerPoolReal {
         let (stream_killer_tx, stream_killer_rx) = mpsc::channel();
         let (stream_adder_tx, stream_adder_rx) = mpsc::channel();
+        let (udp_socket_killer_tx, udp_socket_killer_rx) = mpsc::channel();
         StreamHandlerPoolReal {
             inner: Arc::new(Mutex::new(StreamHandlerPoolRealInner {
                 establisher_factory: Box::new(StreamEstablisherFactoryReal {
@@ -80,6 +113,9 @@ impl StreamHandlerPoolReal {
                     stream_killer_tx,
                     proxy_client_subs: proxy_client_subs.clone(),
                     logger: Logger::new("ProxyClient"),
+                    stream_idle_timeout_opt,
+                    protocol_packs: protocol_packs.clone(),
+                    read_gates,
                 }),
                 accountant_sub,
                 proxy_client_subs,
@@ -88,9 +124,14 @@ impl StreamHandlerPoolReal {
                 logger: Logger::new("ProxyClient"),
                 exit_service_rate,
                 exit_byte_rate,
+                udp_sockets: HashMap::new(),
+                udp_socket_killer_tx,
+                udp_idle_timeout: UDP_IDLE_TIMEOUT,
+                protocol_packs,
             })),
             stream_adder_rx,
             stream_killer_rx,
+            udp_socket_killer_rx,
         }
     }
 
@@ -112,7 +153,9 @@ impl StreamHandlerPoolReal {
                 actix::spawn(future);
             }
             None => {
-                if payload.sequenced_packet.data.is_empty() {
+                if payload.sequenced_packet.data.is_empty()
+                    && !Self::server_speaks_first(&payload, &inner_arc)
+                {
                     debug!(
                         Self::make_logger_copy(&inner_arc_1),
                         "Empty request payload received for nonexistent stream {:?} - ignoring",
@@ -140,6 +183,18 @@ impl StreamHandlerPoolReal {
         };
     }
 
+    fn server_speaks_first(
+        payload: &ClientRequestPayload_0v1,
+        inner_arc: &Arc<Mutex<StreamHandlerPoolRealInner>>,
+    ) -> bool {
+        inner_arc
+            .lock()
+            .expect("Stream handler pool is poisoned")
+            .protocol_packs
+            .pack_for(payload.protocol)
+            .server_speaks_first()
+    }
+
     fn clean_up_bad_stream(
         inner_arc: Arc<Mutex<StreamHandlerPoolRealInner>>,
         stream_key: &StreamKey,
@@ -191,28 +246,153 @@ impl StreamHandlerPoolReal {
                     ),
                 }
             }
-            if payload_size > 0 {
-                match paying_wallet {
-                    Some(wallet) => inner
-                        .accountant_sub
-                        .try_send(ReportExitServiceProvidedMessage {
-                            paying_wallet: wallet,
-                            payload_size,
-                            service_rate: inner.exit_service_rate,
-                            byte_rate: inner.exit_byte_rate,
-                        })
-                        .expect("Accountant is dead"),
-                    // This log is here mostly for testing, to prove that no Accountant message is sent in the no-wallet case
-                    None => debug!(
-                        inner.logger,
-                        "Sent {}-byte request without consuming wallet for free", payload_size
-                    ),
-                }
-            }
+            Self::report_exit_service_provided(&inner, paying_wallet, payload_size);
             Ok(())
         })
     }
 
+    fn report_exit_service_provided(
+        inner: &StreamHandlerPoolRealInner,
+        paying_wallet: Option<Wallet>,
+        payload_size: usize,
+    ) {
+        if payload_size > 0 {
+            match paying_wallet {
+                Some(wallet) => inner
+                    .accountant_sub
+                    .try_send(ReportExitServiceProvidedMessage {
+                        paying_wallet: wallet,
+                        payload_size,
+                        service_rate: inner.exit_service_rate,
+                        byte_rate: inner.exit_byte_rate,
+                    })
+                    .expect("Accountant is dead"),
+                // This log is here mostly for testing, to prove that no Accountant message is sent in the no-wallet case
+                None => debug!(
+                    inner.logger,
+                    "Sent {}-byte request without consuming wallet for free", payload_size
+                ),
+            }
+        }
+    }
+
+    fn process_udp_package(
+        payload: ClientUdpRequestPayload_0v1,
+        paying_wallet: Option<Wallet>,
+        inner_arc: Arc<Mutex<StreamHandlerPoolRealInner>>,
+    ) {
+        let target_hostname = payload.target_hostname.clone();
+        let target_port = payload.target_port;
+        let ip_addrs_future: Box<dyn Future<Item = Vec<IpAddr>, Error = String>> =
+            match Self::parse_ip(&target_hostname) {
+                Ok(ip_addr) => Box::new(future::ok(vec![ip_addr])),
+                Err(_) => Box::new(
+                    inner_arc
+                        .lock()
+                        .expect("Stream handler pool is poisoned")
+                        .resolver
+                        .lookup_ip(&Self::make_fqdn(&target_hostname))
+                        .map(|lookup_ip| lookup_ip.iter().collect())
+                        .map_err(move |e| {
+                            format!(
+                                "Could not find IP address for host {}: {}",
+                                target_hostname, e
+                            )
+                        }),
+                ),
+            };
+        let logger = Self::make_logger_copy(&inner_arc);
+        let future = ip_addrs_future
+            .and_then(move |ip_addrs| match ip_addrs.first() {
+                Some(ip_addr) => Self::send_datagram(
+                    payload,
+                    SocketAddr::new(*ip_addr, target_port),
+                    paying_wallet,
+                    inner_arc,
+                ),
+                None => Err(format!(
+                    "No IP address found for host {}",
+                    payload.target_hostname
+                )),
+            })
+            .map_err(move |error| warning!(logger, "Dropping UDP datagram: {}", error));
+        actix::spawn(future);
+    }
+
+    fn send_datagram(
+        payload: ClientUdpRequestPayload_0v1,
+        target: SocketAddr,
+        paying_wallet: Option<Wallet>,
+        inner_arc: Arc<Mutex<StreamHandlerPoolRealInner>>,
+    ) -> Result<(), String> {
+        let mut inner = inner_arc.lock().expect("Stream handler pool is poisoned");
+        let stream_key = payload.stream_key;
+        let socket = match inner.udp_sockets.remove(&stream_key) {
+            Some(socket) => socket,
+            None => Self::open_udp_socket(stream_key, target, &inner)
+                .map_err(|e| format!("Could not open UDP socket toward {}: {}", target, e))?,
+        };
+        let payload_size = payload.data.len();
+        let send_result = socket.send_to(payload.data.as_slice(), target);
+        inner.udp_sockets.insert(stream_key, socket);
+        send_result.map_err(|e| {
+            format!(
+                "Could not send {}-byte datagram to {}: {}",
+                payload_size, target, e
+            )
+        })?;
+        Self::report_exit_service_provided(&inner, paying_wallet, payload_size);
+        Ok(())
+    }
+
+    fn open_udp_socket(
+        stream_key: StreamKey,
+        target: SocketAddr,
+        inner: &StreamHandlerPoolRealInner,
+    ) -> io::Result<UdpSocket> {
+        let local_ip = match target {
+            SocketAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            SocketAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+        };
+        let socket = UdpSocket::bind(SocketAddr::new(local_ip, 0))?;
+        socket.set_read_timeout(Some(inner.udp_idle_timeout))?;
+        let reader = socket.try_clone()?;
+        let inbound_server_datagram_sub = inner.proxy_client_subs.inbound_server_datagram.clone();
+        let udp_socket_killer_tx = inner.udp_socket_killer_tx.clone();
+        let logger = inner.logger.clone();
+        let local_addr = socket.local_addr()?;
+        debug!(
+            logger,
+            "Opened UDP socket {} for stream {}", local_addr, stream_key
+        );
+        thread::spawn(move || {
+            let mut buffer = [0u8; MAX_DATAGRAM_SIZE];
+            loop {
+                match reader.recv_from(&mut buffer) {
+                    Ok((len, source)) => {
+                        let datagram = InboundServerDatagram {
+                            stream_key,
+                            source,
+                            data: buffer[..len].to_vec(),
+                        };
+                        if inbound_server_datagram_sub.try_send(datagram).is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        debug!(
+                            logger,
+                            "Closing UDP socket for stream {}: {}", stream_key, e
+                        );
+                        break;
+                    }
+                }
+            }
+            let _ = udp_socket_killer_tx.send(stream_key);
+        });
+        Ok(socket)
+    }
+
     fn make_stream_with_key(
         payload: &ClientRequestPayload_0v1,
         inner_arc: Arc<Mutex<StreamHandlerPoolRealInner>>,
@@ -397,30 +577,30 @@ impl StreamHandlerPoolReal {
     fn do_housekeeping(&self) {
         self.clean_up_dead_streams();
         self.add_new_streams();
+        self.clean_up_idle_udp_sockets();
+    }
+
+    fn clean_up_idle_udp_sockets(&self) {
+        let mut inner = self.inner.lock().expect("Stream handler pool is poisoned");
+        while let Ok(stream_key) = self.udp_socket_killer_rx.try_recv() {
+            if inner.udp_sockets.remove(&stream_key).is_some() {
+                debug!(
+                    inner.logger,
+                    "Removed idle UDP socket for stream {}", stream_key
+                );
+            }
+        }
     }
 
     fn clean_up_dead_streams(&self) {
         let mut inner = self.inner.lock().expect("Stream handler pool is poisoned");
-        while let Ok((stream_key, sequence_number)) = self.stream_killer_rx.try_recv() {
+        while let Ok(stream_key) = self.stream_killer_rx.try_recv() {
             match inner.stream_writer_channels.remove(&stream_key) {
-                Some(writer_channel) => {
-                    inner
-                        .proxy_client_subs
-                        .inbound_server_data
-                        .try_send(InboundServerData {
-                            stream_key,
-                            last_data: true,
-                            sequence_number,
-                            source: writer_channel.peer_addr(),
-                            data: vec![],
-                        })
-                        .expect("ProxyClient is dead");
-                    debug!(
-                        inner.logger,
-                        "Killed StreamWriter to {} and sent server-drop report",
-                        writer_channel.peer_addr()
-                    )
-                }
+                Some(writer_channel) => debug!(
+                    inner.logger,
+                    "Killed StreamWriter to {}",
+                    writer_channel.peer_addr()
+                ),
                 None => debug!(
                     inner.logger,
                     "Tried to kill StreamWriter for key {:?}, but it was already gone", stream_key
@@ -451,6 +631,7 @@ impl StreamHandlerPoolReal {
 }
 
 pub trait StreamHandlerPoolFactory {
+    #[allow(clippy::too_many_arguments)]
     fn make(
         &self,
         resolver: Box<dyn ResolverWrapper>,
@@ -459,6 +640,9 @@ pub trait StreamHandlerPoolFactory {
         proxy_client_subs: ProxyClientSubs,
         exit_service_rate: u64,
         exit_byte_rate: u64,
+        stream_idle_timeout_opt: Option<Duration>,
+        protocol_packs: Arc<ProtocolPackRegistry>,
+        read_gates: ReadGates,
     ) -> Box<dyn StreamHandlerPool>;
 }
 
@@ -473,6 +657,9 @@ impl StreamHandlerPoolFactory for StreamHandlerPoolFactoryReal {
         proxy_client_subs: ProxyClientSubs,
         exit_service_rate: u64,
         exit_byte_rate: u64,
+        stream_idle_timeout_opt: Option<Duration>,
+        protocol_packs: Arc<ProtocolPackRegistry>,
+        read_gates: ReadGates,
     ) -> Box<dyn StreamHandlerPool> {
         Box::new(StreamHandlerPoolReal::new(
             resolver,
@@ -481,6 +668,9 @@ impl StreamHandlerPoolFactory for StreamHandlerPoolFactoryReal {
             proxy_client_subs,
             exit_service_rate,
             exit_byte_rate,
+            stream_idle_timeout_opt,
+            protocol_packs,
+            read_gates,
         ))
     }
 }
@@ -492,6 +682,7 @@ mod tests {
     use crate::proxy_client::local_test_utils::ResolverWrapperMock;
     use crate::proxy_client::stream_establisher::StreamEstablisher;
     use crate::sub_lib::channel_wrappers::FuturesChannelFactoryReal;
+    use crate::sub_lib::cryptde::PlainData;
     use crate::sub_lib::cryptde::PublicKey;
     use crate::sub_lib::hopper::ExpiredCoresPackage;
     use crate::sub_lib::hopper::MessageType;
@@ -511,7 +702,7 @@ mod tests {
     use crate::test_utils::tokio_wrapper_mocks::WriteHalfWrapperMock;
     use crate::test_utils::{await_messages, make_wallet};
     use actix::System;
-    use masq_lib::constants::HTTP_PORT;
+    use masq_lib::constants::{HTTP_PORT, SMTP_SUBMISSION_PORT};
     use std::cell::RefCell;
     use std::io::Error;
     use std::io::ErrorKind;
@@ -572,6 +763,9 @@ mod tests {
                 proxy_client_sub: peer_actors.proxy_client.inbound_server_data.clone(),
                 logger: logger.clone(),
                 channel_factory: Box::new(FuturesChannelFactoryMock::default()),
+                stream_idle_timeout_opt: None,
+                protocol_packs: Arc::new(ProtocolPackRegistry::new()),
+                read_gates: ReadGates::new(),
             };
             let inner = StreamHandlerPoolRealInner {
                 accountant_sub: peer_actors.accountant.report_exit_service_provided.clone(),
@@ -584,6 +778,10 @@ mod tests {
                 }),
                 exit_service_rate: Default::default(),
                 exit_byte_rate: Default::default(),
+                udp_sockets: HashMap::new(),
+                udp_socket_killer_tx: mpsc::channel().0,
+                udp_idle_timeout: UDP_IDLE_TIMEOUT,
+                protocol_packs: Arc::new(ProtocolPackRegistry::new()),
             };
             let payload = ClientRequestPayload_0v1 {
                 stream_key,
@@ -592,6 +790,7 @@ mod tests {
                 target_port: HTTP_PORT,
                 protocol: ProxyProtocol::HTTP,
                 originator_public_key: cryptde.public_key().clone(),
+                is_resumption: false,
             };
 
             StreamHandlerPoolReal::process_package(payload, None, Arc::new(Mutex::new(inner)));
@@ -625,6 +824,7 @@ mod tests {
             target_port: HTTP_PORT,
             protocol: ProxyProtocol::HTTP,
             originator_public_key: PublicKey::new(&b"men's souls"[..]),
+            is_resumption: false,
         };
         let write_parameters = Arc::new(Mutex::new(vec![]));
         let tx_to_write = Box::new(
@@ -650,6 +850,9 @@ mod tests {
                 peer_actors.proxy_client.clone(),
                 100,
                 200,
+                None,
+                Arc::new(ProtocolPackRegistry::new()),
+                ReadGates::new(),
             );
             subject
                 .inner
@@ -687,6 +890,7 @@ mod tests {
                 target_port: HTTP_PORT,
                 protocol: ProxyProtocol::HTTP,
                 originator_public_key: originator_key,
+                is_resumption: false,
             };
             let package = ExpiredCoresPackage::new(
                 SocketAddr::from_str("1.2.3.4:1234").unwrap(),
@@ -711,6 +915,9 @@ mod tests {
                 peer_actors.proxy_client.clone(),
                 100,
                 200,
+                None,
+                Arc::new(ProtocolPackRegistry::new()),
+                ReadGates::new(),
             );
             subject
                 .inner
@@ -756,6 +963,7 @@ mod tests {
                 target_port: HTTP_PORT,
                 protocol: ProxyProtocol::HTTP,
                 originator_public_key: PublicKey::new(&b"men's souls"[..]),
+                is_resumption: false,
             };
             let package = ExpiredCoresPackage::new(
                 SocketAddr::from_str("1.2.3.4:1234").unwrap(),
@@ -793,6 +1001,9 @@ mod tests {
                 peer_actors.proxy_client.clone(),
                 100,
                 200,
+                None,
+                Arc::new(ProtocolPackRegistry::new()),
+                ReadGates::new(),
             );
             let (stream_killer_tx, stream_killer_rx) = mpsc::channel();
             subject.stream_killer_rx = stream_killer_rx;
@@ -812,6 +1023,9 @@ mod tests {
                     proxy_client_sub: inner.proxy_client_subs.inbound_server_data.clone(),
                     logger: inner.logger.clone(),
                     channel_factory: Box::new(FuturesChannelFactoryReal {}),
+                    stream_idle_timeout_opt: None,
+                    protocol_packs: Arc::new(ProtocolPackRegistry::new()),
+                    read_gates: ReadGates::new(),
                 };
 
                 inner.establisher_factory = Box::new(StreamEstablisherFactoryMock {
@@ -865,6 +1079,7 @@ mod tests {
                 target_port: HTTP_PORT,
                 protocol: ProxyProtocol::HTTP,
                 originator_public_key: PublicKey::new(&b"men's souls"[..]),
+                is_resumption: false,
             };
             let package = ExpiredCoresPackage::new(
                 SocketAddr::from_str("1.2.3.4:1234").unwrap(),
@@ -902,6 +1117,9 @@ mod tests {
                 peer_actors.proxy_client.clone(),
                 100,
                 200,
+                None,
+                Arc::new(ProtocolPackRegistry::new()),
+                ReadGates::new(),
             );
             let (stream_killer_tx, stream_killer_rx) = mpsc::channel();
             subject.stream_killer_rx = stream_killer_rx;
@@ -921,6 +1139,9 @@ mod tests {
                     proxy_client_sub: inner.proxy_client_subs.inbound_server_data.clone(),
                     logger: inner.logger.clone(),
                     channel_factory: Box::new(FuturesChannelFactoryReal {}),
+                    stream_idle_timeout_opt: None,
+                    protocol_packs: Arc::new(ProtocolPackRegistry::new()),
+                    read_gates: ReadGates::new(),
                 };
 
                 inner.establisher_factory = Box::new(StreamEstablisherFactoryMock {
@@ -972,6 +1193,7 @@ mod tests {
                 target_port: HTTP_PORT,
                 protocol: ProxyProtocol::HTTP,
                 originator_public_key: originator_key,
+                is_resumption: false,
             };
             let package = ExpiredCoresPackage::new(
                 SocketAddr::from_str("1.2.3.4:1234").unwrap(),
@@ -989,6 +1211,9 @@ mod tests {
                 peer_actors.proxy_client.clone(),
                 100,
                 200,
+                None,
+                Arc::new(ProtocolPackRegistry::new()),
+                ReadGates::new(),
             );
 
             run_process_package_in_actix(subject, package);
@@ -1036,6 +1261,7 @@ mod tests {
                 target_port: HTTP_PORT,
                 protocol: ProxyProtocol::HTTP,
                 originator_public_key: PublicKey::new(&b"men's souls"[..]),
+                is_resumption: false,
             };
             let package = ExpiredCoresPackage::new(
                 SocketAddr::from_str("1.2.3.4:1234").unwrap(),
@@ -1073,6 +1299,9 @@ mod tests {
                 peer_actors.proxy_client.clone(),
                 100,
                 200,
+                None,
+                Arc::new(ProtocolPackRegistry::new()),
+                ReadGates::new(),
             );
             let (stream_killer_tx, stream_killer_rx) = mpsc::channel();
             subject.stream_killer_rx = stream_killer_rx;
@@ -1092,6 +1321,9 @@ mod tests {
                     proxy_client_sub: inner.proxy_client_subs.inbound_server_data.clone(),
                     logger: inner.logger.clone(),
                     channel_factory: Box::new(FuturesChannelFactoryReal {}),
+                    stream_idle_timeout_opt: None,
+                    protocol_packs: Arc::new(ProtocolPackRegistry::new()),
+                    read_gates: ReadGates::new(),
                 };
 
                 inner.establisher_factory = Box::new(StreamEstablisherFactoryMock {
@@ -1144,6 +1376,7 @@ mod tests {
                 target_port: HTTP_PORT,
                 protocol: ProxyProtocol::HTTP,
                 originator_public_key: originator_key,
+                is_resumption: false,
             };
             let package = ExpiredCoresPackage::new(
                 SocketAddr::from_str("1.2.3.4:1234").unwrap(),
@@ -1166,6 +1399,9 @@ mod tests {
                 peer_actors.proxy_client.clone(),
                 100,
                 200,
+                None,
+                Arc::new(ProtocolPackRegistry::new()),
+                ReadGates::new(),
             );
             let (stream_killer_tx, stream_killer_rx) = mpsc::channel();
             subject.stream_killer_rx = stream_killer_rx;
@@ -1181,6 +1417,9 @@ mod tests {
                 proxy_client_sub,
                 logger: subject.inner.lock().unwrap().logger.clone(),
                 channel_factory: Box::new(FuturesChannelFactoryReal {}),
+                stream_idle_timeout_opt: None,
+                protocol_packs: Arc::new(ProtocolPackRegistry::new()),
+                read_gates: ReadGates::new(),
             };
 
             subject.inner.lock().unwrap().establisher_factory =
@@ -1230,6 +1469,7 @@ mod tests {
                 target_port: HTTP_PORT,
                 protocol: ProxyProtocol::HTTP,
                 originator_public_key: PublicKey::new(&b"men's souls"[..]),
+                is_resumption: false,
             };
 
             let package = ExpiredCoresPackage::new(
@@ -1266,6 +1506,9 @@ mod tests {
                 peer_actors.proxy_client.clone(),
                 100,
                 200,
+                None,
+                Arc::new(ProtocolPackRegistry::new()),
+                ReadGates::new(),
             );
 
             let peer_addr = SocketAddr::from_str("3.4.5.6:80").unwrap();
@@ -1297,6 +1540,9 @@ mod tests {
                             }),
                         )],
                     }),
+                    stream_idle_timeout_opt: None,
+                    protocol_packs: Arc::new(ProtocolPackRegistry::new()),
+                    read_gates: ReadGates::new(),
                 };
 
                 inner.establisher_factory = Box::new(StreamEstablisherFactoryMock {
@@ -1339,6 +1585,7 @@ mod tests {
                 target_port: HTTP_PORT,
                 protocol: ProxyProtocol::HTTP,
                 originator_public_key: originator_key,
+                is_resumption: false,
             };
             let package = ExpiredCoresPackage::new(
                 SocketAddr::from_str("1.2.3.4:1234").unwrap(),
@@ -1359,6 +1606,9 @@ mod tests {
                 peer_actors.proxy_client.clone(),
                 100,
                 200,
+                None,
+                Arc::new(ProtocolPackRegistry::new()),
+                ReadGates::new(),
             );
             run_process_package_in_actix(subject, package);
         });
@@ -1400,6 +1650,7 @@ mod tests {
             target_port: HTTP_PORT,
             protocol: ProxyProtocol::HTTP,
             originator_public_key: PublicKey::new(&b"men's souls"[..]),
+            is_resumption: false,
         };
         let package = ExpiredCoresPackage::new(
             SocketAddr::from_str("1.2.3.4:1234").unwrap(),
@@ -1427,6 +1678,9 @@ mod tests {
                 peer_actors.proxy_client.clone(),
                 100,
                 200,
+                None,
+                Arc::new(ProtocolPackRegistry::new()),
+                ReadGates::new(),
             );
             subject
                 .inner
@@ -1468,6 +1722,7 @@ mod tests {
                 target_port: HTTP_PORT,
                 protocol: ProxyProtocol::HTTP,
                 originator_public_key: PublicKey::new(&b"booga"[..]),
+                is_resumption: false,
             };
             let package = ExpiredCoresPackage::new(
                 SocketAddr::from_str("1.2.3.4:1234").unwrap(),
@@ -1484,6 +1739,9 @@ mod tests {
                 peer_actors.proxy_client.clone(),
                 100,
                 200,
+                None,
+                Arc::new(ProtocolPackRegistry::new()),
+                ReadGates::new(),
             );
 
             subject.inner.lock().unwrap().establisher_factory =
@@ -1509,7 +1767,59 @@ mod tests {
     }
 
     #[test]
-    fn clean_up_dead_streams_sends_server_drop_report_if_dead_stream_is_in_map() {
+    fn process_package_tries_to_open_connection_for_zero_length_data_when_server_speaks_first() {
+        init_test_logging();
+        let cryptde = main_cryptde();
+        let (proxy_client, proxy_client_awaiter, _) = make_recorder();
+        thread::spawn(move || {
+            let peer_actors = peer_actors_builder().proxy_client(proxy_client).build();
+            let client_request_payload = ClientRequestPayload_0v1 {
+                stream_key: make_meaningless_stream_key(),
+                sequenced_packet: SequencedPacket {
+                    data: vec![],
+                    sequence_number: 0,
+                    last_data: false,
+                },
+                target_hostname: None,
+                target_port: SMTP_SUBMISSION_PORT,
+                protocol: ProxyProtocol::SMTP,
+                originator_public_key: PublicKey::new(&b"booga"[..]),
+                is_resumption: false,
+            };
+            let package = ExpiredCoresPackage::new(
+                SocketAddr::from_str("1.2.3.4:1234").unwrap(),
+                Some(make_wallet("consuming")),
+                make_meaningless_route(),
+                client_request_payload.into(),
+                0,
+            );
+            let subject = StreamHandlerPoolReal::new(
+                Box::new(ResolverWrapperMock::new()),
+                cryptde,
+                peer_actors.accountant.report_exit_service_provided.clone(),
+                peer_actors.proxy_client,
+                100,
+                200,
+                None,
+                Arc::new(ProtocolPackRegistry::new()),
+                ReadGates::new(),
+            );
+
+            run_process_package_in_actix(subject, package);
+        });
+
+        proxy_client_awaiter.await_message_count(1);
+        TestLogHandler::new().exists_log_containing(
+            format!(
+                "ERROR: ProxyClient: Cannot open new stream with key {:?}: no hostname supplied",
+                make_meaningless_stream_key()
+            )
+            .as_str(),
+        );
+    }
+
+    #[test]
+    fn clean_up_dead_streams_removes_dead_stream_writer_without_reporting() {
         let system = System::new("test");
         let (proxy_client, _, proxy_client_recording_arc) = make_recorder();
         let peer_actors = peer_actors_builder().proxy_client(proxy_client).build();
@@ -1520,6 +1830,9 @@ mod tests {
             peer_actors.proxy_client,
             0,
             0,
+            None,
+            Arc::new(ProtocolPackRegistry::new()),
+            ReadGates::new(),
         );
         let (stream_killer_tx, stream_killer_rx) = mpsc::channel();
         subject.stream_killer_rx = stream_killer_rx;
@@ -1531,24 +1844,17 @@ mod tests {
                 .stream_writer_channels
                 .insert(stream_key, Box::new(SenderWrapperMock::new(peer_addr)));
         }
-        stream_killer_tx.send((stream_key, 47)).unwrap();
+        stream_killer_tx.send(stream_key).unwrap();
 
         subject.clean_up_dead_streams();
 
         System::current().stop_with_code(0);
         system.run();
+        let inner = subject.inner.lock().unwrap();
+        assert!(!inner.stream_writer_channels.contains_key(&stream_key));
+        // The StreamReader sends the server-drop report itself before it asks for the kill
         let proxy_client_recording = proxy_client_recording_arc.lock().unwrap();
-        let report = proxy_client_recording.get_record::<InboundServerData>(0);
-        assert_eq!(
-            report,
-            &InboundServerData {
-                stream_key,
-                last_data: true,
-                sequence_number: 47,
-                source: peer_addr,
-                data: vec![]
-            }
-        );
+        assert_eq!(proxy_client_recording.len(), 0);
     }
 
     #[test]
@@ -1563,11 +1869,14 @@ mod tests {
             peer_actors.proxy_client,
             0,
             0,
+            None,
+            Arc::new(ProtocolPackRegistry::new()),
+            ReadGates::new(),
         );
         let (stream_killer_tx, stream_killer_rx) = mpsc::channel();
         subject.stream_killer_rx = stream_killer_rx;
         let stream_key = make_meaningless_stream_key();
-        stream_killer_tx.send((stream_key, 47)).unwrap();
+        stream_killer_tx.send(stream_key).unwrap();
 
         subject.clean_up_dead_streams();
 
@@ -1576,4 +1885,115 @@ mod tests {
         let proxy_client_recording = proxy_client_recording_arc.lock().unwrap();
         assert_eq!(proxy_client_recording.len(), 0);
     }
+
+    #[test]
+    fn udp_package_is_sent_to_target_and_its_reply_comes_back() {
+        let cryptde = main_cryptde();
+        let (proxy_client, proxy_client_awaiter, proxy_client_recording_arc) = make_recorder();
+        let (accountant, accountant_awaiter, accountant_recording_arc) = make_recorder();
+        let echo_server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let echo_addr = echo_server.local_addr().unwrap();
+        thread::spawn(move || {
+            let mut buffer = [0u8; 100];
+            let (len, peer_addr) = echo_server.recv_from(&mut buffer).unwrap();
+            let mut reply = b"reply to ".to_vec();
+            reply.extend(&buffer[..len]);
+            echo_server.send_to(&reply, peer_addr).unwrap();
+        });
+        let stream_key = make_meaningless_stream_key();
+        thread::spawn(move || {
+            let system = System::new("udp_package_is_sent_to_target_and_its_reply_comes_back");
+            let peer_actors = peer_actors_builder()
+                .proxy_client(proxy_client)
+                .accountant(accountant)
+                .build();
+            let subject = StreamHandlerPoolReal::new(
+                Box::new(ResolverWrapperMock::new()),
+                cryptde,
+                peer_actors.accountant.report_exit_service_provided.clone(),
+                peer_actors.proxy_client,
+                100,
+                200,
+                None,
+                Arc::new(ProtocolPackRegistry::new()),
+                ReadGates::new(),
+            );
+
+            subject.process_udp_package(
+                ClientUdpRequestPayload_0v1 {
+                    stream_key,
+                    target_hostname: "127.0.0.1".to_string(),
+                    target_port: echo_addr.port(),
+                    data: PlainData::new(b"quic hello"),
+                    originator_public_key: PublicKey::new(&b"men's souls"[..]),
+                },
+                Some(make_wallet("consuming")),
+            );
+
+            system.run();
+        });
+
+        proxy_client_awaiter.await_message_count(1);
+        accountant_awaiter.await_message_count(1);
+        let proxy_client_recording = proxy_client_recording_arc.lock().unwrap();
+        assert_eq!(
+            proxy_client_recording.get_record::<InboundServerDatagram>(0),
+            &InboundServerDatagram {
+                stream_key,
+                source: echo_addr,
+                data: b"reply to quic hello".to_vec(),
+            }
+        );
+        let accountant_recording = accountant_recording_arc.lock().unwrap();
+        assert_eq!(
+            accountant_recording.get_record::<ReportExitServiceProvidedMessage>(0),
+            &ReportExitServiceProvidedMessage {
+                paying_wallet: make_wallet("consuming"),
+                payload_size: 10,
+                service_rate: 100,
+                byte_rate: 200,
+            }
+        );
+    }
+
+    #[test]
+    fn udp_package_for_unresolvable_host_is_logged_and_dropped() {
+        init_test_logging();
+        let cryptde = main_cryptde();
+        let (accountant, _, accountant_recording_arc) = make_recorder();
+        let system = System::new("udp_package_for_unresolvable_host_is_logged_and_dropped");
+        let peer_actors = peer_actors_builder().accountant(accountant).build();
+        let resolver = ResolverWrapperMock::new().lookup_ip_failure(ResolveErrorKind::Io.into());
+        let subject = StreamHandlerPoolReal::new(
+            Box::new(resolver),
+            cryptde,
+            peer_actors.accountant.report_exit_service_provided.clone(),
+            peer_actors.proxy_client,
+            100,
+            200,
+            None,
+            Arc::new(ProtocolPackRegistry::new()),
+            ReadGates::new(),
+        );
+
+        subject.process_udp_package(
+            ClientUdpRequestPayload_0v1 {
+                stream_key: make_meaningless_stream_key(),
+                target_hostname: "nowhere.com".to_string(),
+                target_port: 443,
+                data: PlainData::new(b"quic hello"),
+                originator_public_key: PublicKey::new(&b"men's souls"[..]),
+            },
+            Some(make_wallet("consuming")),
+        );
+
+        System::current().stop_with_code(0);
+        system.run();
+        TestLogHandler::new().await_log_containing(
+            "WARN: ProxyClient: Dropping UDP datagram: Could not find IP address for host nowhere.com",
+            1000,
+        );
+        assert!(subject.inner.lock().unwrap().udp_sockets.is_empty());
+        assert_eq!(accountant_recording_arc.lock().unwrap().len(), 0);
+    }
 }
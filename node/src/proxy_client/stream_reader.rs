@@ -1,4 +1,5 @@
 // Copyright (c) 2017-2019, Substratum LLC (https://substratum.net) and/or its affiliates. All rights reserved.
+use crate::proxy_client::protocol_pack::ResponseHandler;
 use crate::sub_lib::logger::Logger;
 use crate::sub_lib::proxy_client::InboundServerData;
 use crate::sub_lib::sequencer::Sequencer;
@@ -7,19 +8,110 @@ use crate::sub_lib::tokio_wrappers::ReadHalfWrapper;
 use crate::sub_lib::utils;
 use crate::sub_lib::utils::indicates_dead_stream;
 use actix::Recipient;
+use futures::task::AtomicTask;
+use std::collections::HashMap;
 use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tokio::prelude::Async;
 use tokio::prelude::Future;
+use tokio::timer::Delay;
+
+// The ProxyClient closes a stream's gate when more of the server's data is waiting to go back
+// than the route is carrying, and the StreamReader stops reading until it opens again.
+#[derive(Default)]
+pub struct ReadGate {
+    paused: AtomicBool,
+    task: AtomicTask,
+}
+
+impl ReadGate {
+    pub fn pause(&self) -> bool {
+        !self.paused.swap(true, Ordering::SeqCst)
+    }
+
+    pub fn resume(&self) -> bool {
+        let was_paused = self.paused.swap(false, Ordering::SeqCst);
+        if was_paused {
+            self.task.notify();
+        }
+        was_paused
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    // When the gate is closed, the current task is woken up once it opens
+    fn poll_open(&self) -> bool {
+        if !self.is_paused() {
+            return true;
+        }
+        self.task.register();
+        !self.is_paused()
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct ReadGates {
+    gates: Arc<Mutex<HashMap<StreamKey, Arc<ReadGate>>>>,
+}
+
+impl ReadGates {
+    pub fn new() -> ReadGates {
+        ReadGates::default()
+    }
+
+    pub fn open(&self, stream_key: StreamKey) -> Arc<ReadGate> {
+        self.lock()
+            .entry(stream_key)
+            .or_insert_with(|| Arc::new(ReadGate::default()))
+            .clone()
+    }
+
+    // These return true if the gate changed
+    pub fn pause(&self, stream_key: &StreamKey) -> bool {
+        self.lock()
+            .get(stream_key)
+            .map(|gate| gate.pause())
+            .unwrap_or(false)
+    }
+
+    pub fn resume(&self, stream_key: &StreamKey) -> bool {
+        self.lock()
+            .get(stream_key)
+            .map(|gate| gate.resume())
+            .unwrap_or(false)
+    }
+
+    // A StreamReader left paused would never finish, so a gate is opened before it's forgotten
+    pub fn remove(&self, stream_key: &StreamKey) {
+        if let Some(gate) = self.lock().remove(stream_key) {
+            gate.resume();
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<StreamKey, Arc<ReadGate>>> {
+        self.gates.lock().expect("Read gates are poisoned")
+    }
+}
 
 pub struct StreamReader {
     stream_key: StreamKey,
     proxy_client_sub: Recipient<InboundServerData>,
     stream: Box<dyn ReadHalfWrapper>,
-    stream_killer: Sender<(StreamKey, u64)>,
+    stream_killer: Sender<StreamKey>,
     peer_addr: SocketAddr,
     logger: Logger,
     sequencer: Sequencer,
+    // Shared with the StreamWriter, so that traffic in either direction keeps the stream alive
+    last_activity: Arc<Mutex<Instant>>,
+    idle_timeout_opt: Option<Duration>,
+    idle_timer_opt: Option<Delay>,
+    response_handler: Box<dyn ResponseHandler>,
+    read_gate: Arc<ReadGate>,
 }
 
 impl Future for StreamReader {
@@ -29,8 +121,23 @@ impl Future for StreamReader {
     fn poll(&mut self) -> Result<Async<<Self as Future>::Item>, <Self as Future>::Error> {
         let mut buf: [u8; 16384] = [0; 16384];
         loop {
+            if !self.read_gate.poll_open() {
+                return Ok(Async::NotReady);
+            }
             match self.stream.poll_read(&mut buf) {
-                Ok(Async::NotReady) => return Ok(Async::NotReady),
+                Ok(Async::NotReady) => {
+                    if self.has_gone_idle() {
+                        debug!(
+                            self.logger,
+                            "Stream from {} was idle for more than {:?}: closing",
+                            self.peer_addr,
+                            self.idle_timeout_opt.expect("Idle timeout disappeared")
+                        );
+                        self.shutdown();
+                        return Ok(Async::Ready(()));
+                    }
+                    return Ok(Async::NotReady);
+                }
                 Ok(Async::Ready(0)) => {
                     // see RETURN VALUE section of recv man page (Unix)
                     debug!(
@@ -50,8 +157,13 @@ impl Future for StreamReader {
                             utils::to_string(&Vec::from(&buf[0..len]))
                         );
                     }
+                    *self
+                        .last_activity
+                        .lock()
+                        .expect("Stream activity is poisoned") = Instant::now();
                     let stream_key = self.stream_key;
-                    self.send_inbound_server_data(stream_key, Vec::from(&buf[0..len]), false);
+                    let data = self.response_handler.handle(Vec::from(&buf[0..len]));
+                    self.send_inbound_server_data(stream_key, data, false);
                 }
                 Err(e) => {
                     if indicates_dead_stream(e.kind()) {
@@ -77,12 +189,17 @@ impl Future for StreamReader {
 }
 
 impl StreamReader {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         stream_key: StreamKey,
         proxy_client_sub: Recipient<InboundServerData>,
         stream: Box<dyn ReadHalfWrapper>,
-        stream_killer: Sender<(StreamKey, u64)>,
+        stream_killer: Sender<StreamKey>,
         peer_addr: SocketAddr,
+        last_activity: Arc<Mutex<Instant>>,
+        idle_timeout_opt: Option<Duration>,
+        response_handler: Box<dyn ResponseHandler>,
+        read_gate: Arc<ReadGate>,
     ) -> StreamReader {
         StreamReader {
             stream_key,
@@ -92,13 +209,47 @@ impl StreamReader {
             peer_addr,
             logger: Logger::new(&format!("StreamReader for {:?}/{}", stream_key, peer_addr)[..]),
             sequencer: Sequencer::new(),
+            last_activity,
+            idle_timeout_opt,
+            idle_timer_opt: None,
+            response_handler,
+            read_gate,
         }
     }
 
+    // The server-drop report goes out from here, rather than from the StreamHandlerPool, so that
+    // it isn't lost when the client has already closed its half of the stream
     fn shutdown(&mut self) {
-        let _ = self
-            .stream_killer
-            .send((self.stream_key, self.sequencer.next_sequence_number()));
+        let stream_key = self.stream_key;
+        self.send_inbound_server_data(stream_key, vec![], true);
+        let _ = self.stream_killer.send(stream_key);
+    }
+
+    fn has_gone_idle(&mut self) -> bool {
+        let idle_timeout = match self.idle_timeout_opt {
+            Some(idle_timeout) => idle_timeout,
+            None => return false,
+        };
+        let deadline = *self
+            .last_activity
+            .lock()
+            .expect("Stream activity is poisoned")
+            + idle_timeout;
+        if Instant::now() >= deadline {
+            return true;
+        }
+        // Arrange to be polled again at the deadline, in case nothing else wakes us up by then
+        let mut idle_timer = Delay::new(deadline);
+        if let Err(e) = idle_timer.poll() {
+            warning!(
+                self.logger,
+                "Could not schedule idle check for stream from {}: {}",
+                self.peer_addr,
+                e
+            );
+        }
+        self.idle_timer_opt = Some(idle_timer);
+        false
     }
 
     fn send_inbound_server_data(&mut self, stream_key: StreamKey, data: Vec<u8>, last_data: bool) {
@@ -117,6 +268,7 @@ impl StreamReader {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::proxy_client::protocol_pack::Passthrough;
     use crate::test_utils::logging::init_test_logging;
     use crate::test_utils::logging::TestLogHandler;
     use crate::test_utils::make_meaningless_stream_key;
@@ -130,6 +282,7 @@ mod tests {
     use std::str::FromStr;
     use std::sync::mpsc;
     use std::thread;
+    use tokio::prelude::future::lazy;
 
     #[test]
     fn stream_reader_assigns_a_sequence_to_client_response_payloads() {
@@ -179,11 +332,16 @@ mod tests {
             peer_addr: SocketAddr::from_str("8.7.4.3:50").unwrap(),
             logger: Logger::new("test"),
             sequencer: Sequencer::new(),
+            last_activity: Arc::new(Mutex::new(Instant::now())),
+            idle_timeout_opt: None,
+            idle_timer_opt: None,
+            response_handler: Box::new(Passthrough {}),
+            read_gate: Arc::new(ReadGate::default()),
         };
 
         let _res = subject.poll();
 
-        proxy_client_awaiter.await_message_count(3);
+        proxy_client_awaiter.await_message_count(4);
         let proxy_client_recording = proxy_client_recording_arc.lock().unwrap();
         assert_eq!(
             proxy_client_recording.get_record::<InboundServerData>(0),
@@ -215,8 +373,67 @@ mod tests {
                 data: b"4 File not found\r\n\r\nHTTP/1.1 503 Server error\r\n\r\n".to_vec()
             },
         );
+        assert_eq!(
+            proxy_client_recording.get_record::<InboundServerData>(3),
+            &InboundServerData {
+                stream_key: make_meaningless_stream_key(),
+                last_data: true,
+                sequence_number: 3,
+                source: SocketAddr::from_str("8.7.4.3:50").unwrap(),
+                data: vec![]
+            },
+        );
         let stream_killer_parameters = stream_killer_params.try_recv().unwrap();
-        assert_eq!(stream_killer_parameters, (make_meaningless_stream_key(), 3));
+        assert_eq!(stream_killer_parameters, make_meaningless_stream_key());
+    }
+
+    struct ShoutingHandler {}
+
+    impl ResponseHandler for ShoutingHandler {
+        fn handle(&mut self, data: Vec<u8>) -> Vec<u8> {
+            data.to_ascii_uppercase()
+        }
+    }
+
+    #[test]
+    fn stream_reader_passes_server_data_through_its_response_handler() {
+        let (proxy_client, proxy_client_awaiter, proxy_client_recording_arc) = make_recorder();
+        let mut stream = Box::new(ReadHalfWrapperMock::new());
+        stream.poll_read_results = vec![
+            (b"220 ready\r\n".to_vec(), Ok(Async::Ready(11))),
+            (vec![], Ok(Async::Ready(0))),
+        ];
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let system = System::new("test");
+            let peer_actors = peer_actors_builder().proxy_client(proxy_client).build();
+            tx.send(peer_actors.proxy_client.inbound_server_data)
+                .expect("Internal Error");
+            system.run();
+        });
+        let proxy_client_sub = rx.recv().unwrap();
+        let mut subject = StreamReader::new(
+            make_meaningless_stream_key(),
+            proxy_client_sub,
+            stream,
+            mpsc::channel().0,
+            SocketAddr::from_str("8.7.4.3:50").unwrap(),
+            Arc::new(Mutex::new(Instant::now())),
+            None,
+            Box::new(ShoutingHandler {}),
+            Arc::new(ReadGate::default()),
+        );
+
+        let _res = subject.poll();
+
+        proxy_client_awaiter.await_message_count(2);
+        let proxy_client_recording = proxy_client_recording_arc.lock().unwrap();
+        assert_eq!(
+            proxy_client_recording
+                .get_record::<InboundServerData>(0)
+                .data,
+            b"220 READY\r\n".to_vec()
+        );
     }
 
     #[test]
@@ -259,12 +476,17 @@ mod tests {
             peer_addr: SocketAddr::from_str("5.7.9.0:95").unwrap(),
             logger: Logger::new("test"),
             sequencer: Sequencer::new(),
+            last_activity: Arc::new(Mutex::new(Instant::now())),
+            idle_timeout_opt: None,
+            idle_timer_opt: None,
+            response_handler: Box::new(Passthrough {}),
+            read_gate: Arc::new(ReadGate::default()),
         };
 
         let result = subject.poll();
 
         assert_eq!(result, Err(()));
-        proxy_client_awaiter.await_message_count(3);
+        proxy_client_awaiter.await_message_count(4);
         let proxy_client_recording = proxy_client_recording_arc.lock().unwrap();
         assert_eq!(
             proxy_client_recording.get_record::<InboundServerData>(0),
@@ -296,48 +518,212 @@ mod tests {
                 data: b"4 File not found\r\n\r\nHTTP/1.1 503 Server error\r\n\r\n".to_vec()
             }
         );
+        assert_eq!(
+            proxy_client_recording.get_record::<InboundServerData>(3),
+            &InboundServerData {
+                stream_key: make_meaningless_stream_key(),
+                last_data: true,
+                sequence_number: 3,
+                source: SocketAddr::from_str("5.7.9.0:95").unwrap(),
+                data: vec![]
+            }
+        );
 
         let kill_stream_msg = stream_killer_params
             .try_recv()
             .expect("stream was not killed");
-        assert_eq!(kill_stream_msg, (make_meaningless_stream_key(), 3));
+        assert_eq!(kill_stream_msg, make_meaningless_stream_key());
         assert!(stream_killer_params.try_recv().is_err());
     }
 
     #[test]
-    fn receiving_0_bytes_kills_stream() {
+    fn receiving_0_bytes_sends_server_drop_report_and_kills_stream() {
         init_test_logging();
+        let (proxy_client, proxy_client_awaiter, proxy_client_recording_arc) = make_recorder();
         let stream_key = make_meaningless_stream_key();
         let (stream_killer, kill_stream_params) = mpsc::channel();
         let mut stream = ReadHalfWrapperMock::new();
         stream.poll_read_results = vec![(vec![], Ok(Async::Ready(0)))];
-
-        let system = System::new("receiving_0_bytes_sends_empty_cores_response_and_kills_stream");
-        let peer_actors = peer_actors_builder().build();
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let system = System::new("receiving_0_bytes_sends_server_drop_report_and_kills_stream");
+            let peer_actors = peer_actors_builder().proxy_client(proxy_client).build();
+            tx.send(peer_actors.proxy_client.inbound_server_data)
+                .expect("Internal Error");
+            system.run();
+        });
+        let proxy_client_sub = rx.recv().unwrap();
         let mut sequencer = Sequencer::new();
         sequencer.next_sequence_number();
         sequencer.next_sequence_number();
-
         let mut subject = StreamReader {
             stream_key,
-            proxy_client_sub: peer_actors.proxy_client.inbound_server_data,
+            proxy_client_sub,
             stream: Box::new(stream),
             stream_killer,
             peer_addr: SocketAddr::from_str("5.3.4.3:654").unwrap(),
             logger: Logger::new("test"),
             sequencer,
+            last_activity: Arc::new(Mutex::new(Instant::now())),
+            idle_timeout_opt: None,
+            idle_timer_opt: None,
+            response_handler: Box::new(Passthrough {}),
+            read_gate: Arc::new(ReadGate::default()),
         };
-        System::current().stop_with_code(0);
-        system.run();
 
         let result = subject.poll();
 
         assert_eq!(result, Ok(Async::Ready(())));
-        assert_eq!(kill_stream_params.try_recv().unwrap(), (stream_key, 2));
+        proxy_client_awaiter.await_message_count(1);
+        let proxy_client_recording = proxy_client_recording_arc.lock().unwrap();
+        assert_eq!(
+            proxy_client_recording.get_record::<InboundServerData>(0),
+            &InboundServerData {
+                stream_key,
+                last_data: true,
+                sequence_number: 2,
+                source: SocketAddr::from_str("5.3.4.3:654").unwrap(),
+                data: vec![]
+            }
+        );
+        assert_eq!(kill_stream_params.try_recv().unwrap(), stream_key);
         TestLogHandler::new()
             .exists_log_containing("Stream from 5.3.4.3:654 was closed: (0-byte read)");
     }
 
+    #[test]
+    fn stream_that_has_gone_idle_is_closed() {
+        init_test_logging();
+        let (proxy_client, proxy_client_awaiter, proxy_client_recording_arc) = make_recorder();
+        let stream_key = make_meaningless_stream_key();
+        let (stream_killer, kill_stream_params) = mpsc::channel();
+        let mut stream = ReadHalfWrapperMock::new();
+        stream.poll_read_results = vec![(vec![], Ok(Async::NotReady))];
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let system = System::new("stream_that_has_gone_idle_is_closed");
+            let peer_actors = peer_actors_builder().proxy_client(proxy_client).build();
+            tx.send(peer_actors.proxy_client.inbound_server_data)
+                .expect("Internal Error");
+            system.run();
+        });
+        let proxy_client_sub = rx.recv().unwrap();
+        let mut subject = StreamReader::new(
+            stream_key,
+            proxy_client_sub,
+            Box::new(stream),
+            stream_killer,
+            SocketAddr::from_str("5.3.4.3:654").unwrap(),
+            Arc::new(Mutex::new(Instant::now() - Duration::from_secs(11))),
+            Some(Duration::from_secs(10)),
+            Box::new(Passthrough {}),
+            Arc::new(ReadGate::default()),
+        );
+
+        let result = subject.poll();
+
+        assert_eq!(result, Ok(Async::Ready(())));
+        proxy_client_awaiter.await_message_count(1);
+        let proxy_client_recording = proxy_client_recording_arc.lock().unwrap();
+        assert_eq!(
+            proxy_client_recording.get_record::<InboundServerData>(0),
+            &InboundServerData {
+                stream_key,
+                last_data: true,
+                sequence_number: 0,
+                source: SocketAddr::from_str("5.3.4.3:654").unwrap(),
+                data: vec![]
+            }
+        );
+        assert_eq!(kill_stream_params.try_recv().unwrap(), stream_key);
+        TestLogHandler::new()
+            .exists_log_containing("Stream from 5.3.4.3:654 was idle for more than 10s: closing");
+    }
+
+    #[test]
+    fn stream_with_recent_activity_is_not_closed_for_idleness() {
+        let stream_key = make_meaningless_stream_key();
+        let (stream_killer, kill_stream_params) = mpsc::channel();
+        let mut stream = ReadHalfWrapperMock::new();
+        stream.poll_read_results = vec![(vec![], Ok(Async::NotReady))];
+        let _system = System::new("stream_with_recent_activity_is_not_closed_for_idleness");
+        let peer_actors = peer_actors_builder().build();
+        let mut subject = StreamReader::new(
+            stream_key,
+            peer_actors.proxy_client.inbound_server_data,
+            Box::new(stream),
+            stream_killer,
+            SocketAddr::from_str("5.3.4.3:654").unwrap(),
+            Arc::new(Mutex::new(Instant::now() - Duration::from_secs(9))),
+            Some(Duration::from_secs(10)),
+            Box::new(Passthrough {}),
+            Arc::new(ReadGate::default()),
+        );
+        let (tx, rx) = mpsc::channel();
+
+        tokio::run(lazy(move || {
+            tx.send(subject.poll()).unwrap();
+            Ok(())
+        }));
+
+        assert_eq!(rx.recv().unwrap(), Ok(Async::NotReady));
+        assert!(kill_stream_params.try_recv().is_err());
+    }
+
+    #[test]
+    fn paused_stream_reader_does_not_read_until_its_gate_opens() {
+        let stream_key = make_meaningless_stream_key();
+        let mut stream = ReadHalfWrapperMock::new();
+        stream.poll_read_results = vec![(vec![], Ok(Async::NotReady))];
+        let _system = System::new("paused_stream_reader_does_not_read_until_its_gate_opens");
+        let peer_actors = peer_actors_builder().build();
+        let read_gates = ReadGates::new();
+        let mut subject = StreamReader::new(
+            stream_key,
+            peer_actors.proxy_client.inbound_server_data,
+            Box::new(stream),
+            mpsc::channel().0,
+            SocketAddr::from_str("5.3.4.3:654").unwrap(),
+            Arc::new(Mutex::new(Instant::now())),
+            None,
+            Box::new(Passthrough {}),
+            read_gates.open(stream_key),
+        );
+        let paused = read_gates.pause(&stream_key);
+        let paused_again = read_gates.pause(&stream_key);
+        let (tx, rx) = mpsc::channel();
+
+        tokio::run(lazy(move || {
+            tx.send(subject.poll()).unwrap();
+            tx.send(subject.poll()).unwrap();
+            read_gates.resume(&stream_key);
+            tx.send(subject.poll()).unwrap();
+            Ok(())
+        }));
+
+        assert_eq!(paused, true);
+        assert_eq!(paused_again, false);
+        assert_eq!(rx.recv().unwrap(), Ok(Async::NotReady));
+        assert_eq!(rx.recv().unwrap(), Ok(Async::NotReady));
+        assert_eq!(rx.recv().unwrap(), Ok(Async::NotReady));
+    }
+
+    #[test]
+    fn removing_a_read_gate_opens_it() {
+        let stream_key = make_meaningless_stream_key();
+        let subject = ReadGates::new();
+        let gate = subject.open(stream_key);
+        subject.pause(&stream_key);
+        let paused_before = gate.is_paused();
+
+        subject.remove(&stream_key);
+
+        assert_eq!(paused_before, true);
+        assert_eq!(gate.is_paused(), false);
+        assert_eq!(subject.pause(&stream_key), false);
+        assert_eq!(subject.resume(&stream_key), false);
+    }
+
     #[test]
     fn non_dead_stream_read_errors_log_but_do_not_shut_down() {
         init_test_logging();
@@ -374,6 +760,11 @@ mod tests {
             peer_addr: SocketAddr::from_str("6.5.4.1:8325").unwrap(),
             logger: Logger::new("test"),
             sequencer: Sequencer::new(),
+            last_activity: Arc::new(Mutex::new(Instant::now())),
+            idle_timeout_opt: None,
+            idle_timer_opt: None,
+            response_handler: Box::new(Passthrough {}),
+            read_gate: Arc::new(ReadGate::default()),
         };
 
         let result = subject.poll();
@@ -1,4 +1,5 @@
 // Copyright (c) 2017-2019, Substratum LLC (https://substratum.net) and/or its affiliates. All rights reserved.
+use crate::proxy_client::protocol_pack::RequestFramer;
 use crate::sub_lib::channel_wrappers::ReceiverWrapper;
 use crate::sub_lib::logger::Logger;
 use crate::sub_lib::sequence_buffer::SequenceBuffer;
@@ -7,6 +8,8 @@ use crate::sub_lib::stream_key::StreamKey;
 use crate::sub_lib::tokio_wrappers::WriteHalfWrapper;
 use crate::sub_lib::utils::indicates_dead_stream;
 use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 use tokio::prelude::Async;
 use tokio::prelude::Future;
 
@@ -17,6 +20,9 @@ pub struct StreamWriter {
     sequence_buffer: SequenceBuffer,
     rx_to_write: Box<dyn ReceiverWrapper<SequencedPacket>>,
     shutting_down: bool,
+    last_activity: Arc<Mutex<Instant>>,
+    request_framer: Box<dyn RequestFramer>,
+    next_unframed_sequence_number: u64,
 }
 
 impl Future for StreamWriter {
@@ -45,6 +51,8 @@ impl StreamWriter {
         peer_addr: SocketAddr,
         rx_to_write: Box<dyn ReceiverWrapper<SequencedPacket>>,
         stream_key: StreamKey,
+        last_activity: Arc<Mutex<Instant>>,
+        request_framer: Box<dyn RequestFramer>,
     ) -> StreamWriter {
         let name = format!("StreamWriter for {:?}/{}", stream_key, peer_addr);
         let logger = Logger::new(&name[..]);
@@ -55,6 +63,9 @@ impl StreamWriter {
             sequence_buffer: SequenceBuffer::new(),
             rx_to_write,
             shutting_down: false,
+            last_activity,
+            request_framer,
+            next_unframed_sequence_number: 0,
         }
     }
 
@@ -82,12 +93,25 @@ impl StreamWriter {
         }
     }
 
+    // Packets that weren't written all at once come around again, already framed
+    fn frame(&mut self, packet: SequencedPacket) -> SequencedPacket {
+        if packet.sequence_number < self.next_unframed_sequence_number {
+            return packet;
+        }
+        self.next_unframed_sequence_number = packet.sequence_number + 1;
+        SequencedPacket {
+            data: self.request_framer.frame(packet.data),
+            ..packet
+        }
+    }
+
     fn write_from_buffer_to_stream(&mut self) -> Result<Async<()>, ()> {
         loop {
             let packet_opt = self.sequence_buffer.poll();
 
             match packet_opt {
                 Some(packet) => {
+                    let packet = self.frame(packet);
                     debug!(
                         self.logger,
                         "Writing {} bytes over existing stream",
@@ -114,6 +138,10 @@ impl StreamWriter {
                             return Ok(Async::NotReady);
                         }
                         Ok(Async::Ready(bytes_written_count)) => {
+                            *self
+                                .last_activity
+                                .lock()
+                                .expect("Stream activity is poisoned") = Instant::now();
                             debug!(
                                 self.logger,
                                 "Wrote {}/{} bytes of clear data (#{})",
@@ -154,6 +182,8 @@ impl StreamWriter {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::proxy_client::protocol_pack::Passthrough;
+    use crate::proxy_client::smtp_protocol_pack::SmtpRequestFramer;
     use crate::test_utils::channel_wrapper_mocks::ReceiverWrapperMock;
     use crate::test_utils::logging::init_test_logging;
     use crate::test_utils::logging::TestLogHandler;
@@ -164,6 +194,7 @@ mod tests {
     use std::str::FromStr;
     use std::sync::Arc;
     use std::sync::Mutex;
+    use std::time::{Duration, Instant};
 
     #[test]
     fn stream_writer_writes_packets_in_sequenced_order() {
@@ -208,7 +239,14 @@ mod tests {
         let write_params_mutex = writer.poll_write_params.clone();
         let peer_addr = SocketAddr::from_str("2.2.3.4:5678").unwrap();
 
-        let mut subject = StreamWriter::new(Box::new(writer), peer_addr, rx_to_write, stream_key);
+        let mut subject = StreamWriter::new(
+            Box::new(writer),
+            peer_addr,
+            rx_to_write,
+            stream_key,
+            Arc::new(Mutex::new(Instant::now())),
+            Box::new(Passthrough {}),
+        );
 
         let _res = subject.poll();
 
@@ -265,6 +303,33 @@ mod tests {
         ]);
     }
 
+    #[test]
+    fn stream_writer_records_activity_when_it_writes() {
+        let mut rx_to_write = Box::new(ReceiverWrapperMock::new());
+        rx_to_write.poll_results = vec![
+            Ok(Async::Ready(Some(SequencedPacket {
+                data: b"hello".to_vec(),
+                sequence_number: 0,
+                last_data: false,
+            }))),
+            Ok(Async::NotReady),
+        ];
+        let writer = WriteHalfWrapperMock::new().poll_write_result(Ok(Async::Ready(5)));
+        let last_activity = Arc::new(Mutex::new(Instant::now() - Duration::from_secs(60)));
+        let mut subject = StreamWriter::new(
+            Box::new(writer),
+            SocketAddr::from_str("2.2.3.4:5678").unwrap(),
+            rx_to_write,
+            make_meaningless_stream_key(),
+            last_activity.clone(),
+            Box::new(Passthrough {}),
+        );
+
+        let _ = subject.poll();
+
+        assert!(last_activity.lock().unwrap().elapsed() < Duration::from_secs(60));
+    }
+
     #[test]
     fn stream_writer_returns_not_ready_when_the_stream_is_not_ready() {
         let stream_key = make_meaningless_stream_key();
@@ -286,6 +351,8 @@ mod tests {
             SocketAddr::from_str("1.3.3.4:5678").unwrap(),
             rx_to_write,
             stream_key,
+            Arc::new(Mutex::new(Instant::now())),
+            Box::new(Passthrough {}),
         );
 
         let result = subject.poll();
@@ -306,6 +373,8 @@ mod tests {
             SocketAddr::from_str("1.2.4.4:5678").unwrap(),
             rx_to_write,
             stream_key,
+            Arc::new(Mutex::new(Instant::now())),
+            Box::new(Passthrough {}),
         );
 
         let result = subject.poll();
@@ -336,7 +405,14 @@ mod tests {
         let write_params = writer.poll_write_params.clone();
         let peer_addr = SocketAddr::from_str("1.3.3.4:5678").unwrap();
 
-        let mut subject = StreamWriter::new(Box::new(writer), peer_addr, rx_to_write, stream_key);
+        let mut subject = StreamWriter::new(
+            Box::new(writer),
+            peer_addr,
+            rx_to_write,
+            stream_key,
+            Arc::new(Mutex::new(Instant::now())),
+            Box::new(Passthrough {}),
+        );
 
         subject.poll().unwrap();
 
@@ -377,7 +453,14 @@ mod tests {
         let write_params = writer.poll_write_params.clone();
         let peer_addr = SocketAddr::from_str("1.2.3.9:5678").unwrap();
 
-        let mut subject = StreamWriter::new(Box::new(writer), peer_addr, rx_to_write, stream_key);
+        let mut subject = StreamWriter::new(
+            Box::new(writer),
+            peer_addr,
+            rx_to_write,
+            stream_key,
+            Arc::new(Mutex::new(Instant::now())),
+            Box::new(Passthrough {}),
+        );
 
         let result = subject.poll();
 
@@ -406,7 +489,14 @@ mod tests {
 
         let peer_addr = SocketAddr::from_str("1.2.3.4:999").unwrap();
 
-        let mut subject = StreamWriter::new(Box::new(writer), peer_addr, rx_to_write, stream_key);
+        let mut subject = StreamWriter::new(
+            Box::new(writer),
+            peer_addr,
+            rx_to_write,
+            stream_key,
+            Arc::new(Mutex::new(Instant::now())),
+            Box::new(Passthrough {}),
+        );
 
         let result = subject.poll();
 
@@ -424,7 +514,14 @@ mod tests {
         let stream_key = make_meaningless_stream_key();
         let peer_addr = SocketAddr::from_str("4.2.3.4:5678").unwrap();
 
-        let mut subject = StreamWriter::new(Box::new(writer), peer_addr, rx_to_write, stream_key);
+        let mut subject = StreamWriter::new(
+            Box::new(writer),
+            peer_addr,
+            rx_to_write,
+            stream_key,
+            Arc::new(Mutex::new(Instant::now())),
+            Box::new(Passthrough {}),
+        );
 
         subject.poll().unwrap();
     }
@@ -451,6 +548,8 @@ mod tests {
             SocketAddr::from_str("2.3.4.5:80").unwrap(),
             rx_to_write,
             stream_key,
+            Arc::new(Mutex::new(Instant::now())),
+            Box::new(Passthrough {}),
         );
 
         assert!(subject.poll().is_err());
@@ -486,7 +585,14 @@ mod tests {
         let write_params = writer.poll_write_params.clone();
         let peer_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
 
-        let mut subject = StreamWriter::new(Box::new(writer), peer_addr, rx, stream_key);
+        let mut subject = StreamWriter::new(
+            Box::new(writer),
+            peer_addr,
+            rx,
+            stream_key,
+            Arc::new(Mutex::new(Instant::now())),
+            Box::new(Passthrough {}),
+        );
 
         let result = subject.poll();
         assert_eq!(result, Ok(Async::NotReady));
@@ -521,7 +627,14 @@ mod tests {
         let write_params = writer.poll_write_params.clone();
         let peer_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
 
-        let mut subject = StreamWriter::new(Box::new(writer), peer_addr, rx, stream_key);
+        let mut subject = StreamWriter::new(
+            Box::new(writer),
+            peer_addr,
+            rx,
+            stream_key,
+            Arc::new(Mutex::new(Instant::now())),
+            Box::new(Passthrough {}),
+        );
 
         let result = subject.poll();
         assert_eq!(result, Ok(Async::NotReady));
@@ -538,6 +651,40 @@ mod tests {
         assert_eq!(write_params.lock().unwrap().get(2).unwrap(), &b"s".to_vec());
     }
 
+    #[test]
+    fn stream_writer_frames_each_packet_only_once() {
+        let stream_key = make_meaningless_stream_key();
+        let mut rx = Box::new(ReceiverWrapperMock::new());
+        rx.poll_results = vec![
+            Ok(Async::Ready(Some(SequencedPacket::new(
+                b"a\nb\n".to_vec(),
+                0,
+                false,
+            )))),
+            Ok(Async::NotReady),
+        ];
+        let writer = WriteHalfWrapperMock::new()
+            .poll_write_result(Ok(Async::Ready(2)))
+            .poll_write_result(Ok(Async::NotReady));
+        let write_params = writer.poll_write_params.clone();
+        let mut subject = StreamWriter::new(
+            Box::new(writer),
+            SocketAddr::from_str("1.2.3.4:5678").unwrap(),
+            rx,
+            stream_key,
+            Arc::new(Mutex::new(Instant::now())),
+            Box::new(SmtpRequestFramer::default()),
+        );
+
+        let result = subject.poll();
+
+        assert_eq!(result, Ok(Async::NotReady));
+        assert_eq!(
+            *write_params.lock().unwrap(),
+            vec![b"a\r\nb\r\n".to_vec(), b"\nb\r\n".to_vec()]
+        );
+    }
+
     #[test]
     fn stream_writer_shuts_down_stream_after_writing_last_data() {
         init_test_logging();
@@ -564,7 +711,14 @@ mod tests {
         let write_params_mutex = writer.poll_write_params.clone();
         let peer_addr = SocketAddr::from_str("2.2.3.4:5678").unwrap();
 
-        let mut subject = StreamWriter::new(Box::new(writer), peer_addr, rx_to_write, stream_key);
+        let mut subject = StreamWriter::new(
+            Box::new(writer),
+            peer_addr,
+            rx_to_write,
+            stream_key,
+            Arc::new(Mutex::new(Instant::now())),
+            Box::new(Passthrough {}),
+        );
 
         let res = subject.poll();
 
@@ -617,7 +771,14 @@ mod tests {
         let write_params_mutex = writer.poll_write_params.clone();
         let peer_addr = SocketAddr::from_str("2.2.3.4:5678").unwrap();
 
-        let mut subject = StreamWriter::new(Box::new(writer), peer_addr, rx_to_write, stream_key);
+        let mut subject = StreamWriter::new(
+            Box::new(writer),
+            peer_addr,
+            rx_to_write,
+            stream_key,
+            Arc::new(Mutex::new(Instant::now())),
+            Box::new(Passthrough {}),
+        );
 
         let res = subject.poll();
 
@@ -671,7 +832,14 @@ mod tests {
         let write_params_mutex = writer.poll_write_params.clone();
         let peer_addr = SocketAddr::from_str("2.2.3.4:5678").unwrap();
 
-        let mut subject = StreamWriter::new(Box::new(writer), peer_addr, rx_to_write, stream_key);
+        let mut subject = StreamWriter::new(
+            Box::new(writer),
+            peer_addr,
+            rx_to_write,
+            stream_key,
+            Arc::new(Mutex::new(Instant::now())),
+            Box::new(Passthrough {}),
+        );
 
         let res = subject.poll();
 
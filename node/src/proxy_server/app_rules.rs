@@ -0,0 +1,218 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AppAction {
+    // Through the MASQ Network, even if the split-tunnel rules would send it directly
+    Masq,
+    // Straight to its destination over a zero-hop route
+    Direct,
+    // Not at all: the connection is closed
+    Block,
+}
+
+impl FromStr for AppAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "masq" => Ok(AppAction::Masq),
+            "direct" => Ok(AppAction::Direct),
+            "block" => Ok(AppAction::Block),
+            _ => Err(format!(
+                "Application action '{}' is unknown: use masq, direct, or block",
+                s.trim()
+            )),
+        }
+    }
+}
+
+impl fmt::Display for AppAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppAction::Masq => write!(f, "masq"),
+            AppAction::Direct => write!(f, "direct"),
+            AppAction::Block => write!(f, "block"),
+        }
+    }
+}
+
+// <application>:<action>, where the application is the name of a program, without its path
+#[derive(Clone, Debug, PartialEq)]
+pub struct AppRule {
+    pub application: String,
+    pub action: AppAction,
+}
+
+impl FromStr for AppRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rule = s.trim();
+        let (application, action_str) = match rule.rfind(':') {
+            Some(idx) => (&rule[..idx], &rule[(idx + 1)..]),
+            None => {
+                return Err(format!(
+                    "Application rule '{}' should look like <application>:<action>",
+                    rule
+                ))
+            }
+        };
+        let application = Self::normalize(application);
+        if application.is_empty() || application.contains('/') || application.contains('\\') {
+            return Err(format!(
+                "Application rule '{}' needs a program name without a path",
+                rule
+            ));
+        }
+        Ok(AppRule {
+            application,
+            action: AppAction::from_str(action_str)?,
+        })
+    }
+}
+
+impl fmt::Display for AppRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.application, self.action)
+    }
+}
+
+impl AppRule {
+    pub fn matches(&self, application: &str) -> bool {
+        Self::normalize(application) == self.application
+    }
+
+    fn normalize(application: &str) -> String {
+        let application = application.trim().to_lowercase();
+        match application.strip_suffix(".exe") {
+            Some(stem) => stem.to_string(),
+            None => application,
+        }
+    }
+}
+
+// The first rule that matches an application decides what happens to its connections; the
+// connections of applications no rule matches are left to the split-tunnel rules.
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct AppRules {
+    rules: Vec<AppRule>,
+}
+
+impl FromStr for AppRules {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rule_strs = s
+            .split(',')
+            .filter(|rule_str| !rule_str.trim().is_empty())
+            .collect::<Vec<&str>>();
+        Self::from_strs(&rule_strs)
+    }
+}
+
+impl fmt::Display for AppRules {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_strings().join(","))
+    }
+}
+
+impl AppRules {
+    pub fn new(rules: Vec<AppRule>) -> AppRules {
+        AppRules { rules }
+    }
+
+    pub fn from_strs<S: AsRef<str>>(rule_strs: &[S]) -> Result<AppRules, String> {
+        let rules = rule_strs
+            .iter()
+            .map(|rule_str| AppRule::from_str(rule_str.as_ref()))
+            .collect::<Result<Vec<AppRule>, String>>()?;
+        Ok(AppRules::new(rules))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    pub fn to_strings(&self) -> Vec<String> {
+        self.rules.iter().map(|rule| rule.to_string()).collect()
+    }
+
+    pub fn action_for(&self, application: &str) -> Option<AppAction> {
+        self.rules
+            .iter()
+            .find(|rule| rule.matches(application))
+            .map(|rule| rule.action)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rule_is_parsed_and_normalized() {
+        assert_eq!(
+            AppRule::from_str(" Firefox.EXE:Direct "),
+            Ok(AppRule {
+                application: "firefox".to_string(),
+                action: AppAction::Direct
+            })
+        );
+        assert_eq!(
+            AppRule::from_str("torrent-client:block"),
+            Ok(AppRule {
+                application: "torrent-client".to_string(),
+                action: AppAction::Block
+            })
+        );
+    }
+
+    #[test]
+    fn bad_rules_are_rejected() {
+        assert_eq!(
+            AppRule::from_str("firefox"),
+            Err("Application rule 'firefox' should look like <application>:<action>".to_string())
+        );
+        assert_eq!(
+            AppRule::from_str("/usr/bin/firefox:masq"),
+            Err(
+                "Application rule '/usr/bin/firefox:masq' needs a program name without a path"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            AppRule::from_str(":masq"),
+            Err("Application rule ':masq' needs a program name without a path".to_string())
+        );
+        assert_eq!(
+            AppRule::from_str("firefox:booga"),
+            Err("Application action 'booga' is unknown: use masq, direct, or block".to_string())
+        );
+    }
+
+    #[test]
+    fn first_matching_rule_decides() {
+        let subject =
+            AppRules::from_strs(&["firefox:masq", "curl:block", "Firefox:direct"]).unwrap();
+
+        assert_eq!(subject.action_for("firefox"), Some(AppAction::Masq));
+        assert_eq!(subject.action_for("FIREFOX.exe"), Some(AppAction::Masq));
+        assert_eq!(subject.action_for("curl"), Some(AppAction::Block));
+        assert_eq!(subject.action_for("wget"), None);
+        assert_eq!(AppRules::default().action_for("firefox"), None);
+    }
+
+    #[test]
+    fn rules_survive_a_round_trip_through_a_string() {
+        let subject = AppRules::from_strs(&["Firefox:masq", "curl.exe:block"]).unwrap();
+
+        let string = subject.to_string();
+        let result = AppRules::from_str(&string).unwrap();
+
+        assert_eq!(string, "firefox:masq,curl:block".to_string());
+        assert_eq!(result, subject);
+        assert_eq!(AppRules::from_str("").unwrap().is_empty(), true);
+    }
+}
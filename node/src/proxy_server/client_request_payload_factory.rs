@@ -55,6 +55,7 @@ impl ClientRequestPayloadFactory {
             target_port,
             protocol: protocol_pack.proxy_protocol(),
             originator_public_key: cryptde.public_key().clone(),
+            is_resumption: false,
         })
     }
 }
@@ -100,6 +101,7 @@ mod tests {
                 target_port: 2345,
                 protocol: ProxyProtocol::HTTP,
                 originator_public_key: cryptde.public_key().clone(),
+                is_resumption: false,
             })
         );
     }
@@ -134,6 +136,7 @@ mod tests {
                 target_port: HTTP_PORT,
                 protocol: ProxyProtocol::HTTP,
                 originator_public_key: cryptde.public_key().clone(),
+                is_resumption: false,
             })
         );
     }
@@ -187,6 +190,7 @@ mod tests {
                 target_port: 443,
                 protocol: ProxyProtocol::TLS,
                 originator_public_key: cryptde.public_key().clone(),
+                is_resumption: false,
             })
         );
     }
@@ -234,6 +238,7 @@ mod tests {
                 target_port: 443,
                 protocol: ProxyProtocol::TLS,
                 originator_public_key: cryptde.public_key().clone(),
+                is_resumption: false,
             })
         );
     }
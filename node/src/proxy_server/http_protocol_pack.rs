@@ -33,6 +33,10 @@ impl ProtocolPack for HttpProtocolPack {
     fn server_impersonator(&self) -> Box<dyn ServerImpersonator> {
         Box::new(ServerImpersonatorHttp {})
     }
+
+    fn server_speaks_first(&self) -> bool {
+        false
+    }
 }
 
 impl HttpProtocolPack {
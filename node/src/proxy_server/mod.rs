@@ -1,59 +1,113 @@
 // Copyright (c) 2017-2019, Substratum LLC (https://substratum.net) and/or its affiliates. All rights reserved.
 
+pub mod app_rules;
 pub mod client_request_payload_factory;
 pub mod http_protocol_pack;
+pub mod process_identifier;
 pub mod protocol_pack;
 pub mod server_impersonator_http;
+pub mod server_impersonator_smtp;
 pub mod server_impersonator_tls;
+pub mod smtp_protocol_pack;
+pub mod socks5;
+pub mod split_tunnel;
+pub mod stream_resumption;
+pub mod stream_sniffer;
 pub mod tls_protocol_pack;
+pub mod udp_relay;
 
+use crate::database::dao_utils::now_time_t;
+use crate::db_config::persistent_configuration::PersistentConfiguration;
+use crate::proxy_server::app_rules::{AppAction, AppRules};
 use crate::proxy_server::client_request_payload_factory::ClientRequestPayloadFactory;
 use crate::proxy_server::http_protocol_pack::HttpProtocolPack;
-use crate::proxy_server::protocol_pack::{from_ibcd, from_protocol, ProtocolPack};
+use crate::proxy_server::process_identifier::{ProcessIdentifier, ProcessIdentifierReal};
+use crate::proxy_server::protocol_pack::{
+    from_ibcd, from_protocol, from_standard_port, Host, ProtocolPack,
+};
+use crate::proxy_server::socks5::{
+    make_udp_datagram, parse_udp_datagram, Socks5Negotiation, Socks5Outcome,
+};
+use crate::proxy_server::split_tunnel::SplitTunnelRules;
+use crate::proxy_server::stream_resumption::{
+    ResumptionStore, ResumptionToken, RESUMPTION_CHECKPOINT_INTERVAL, RESUMPTION_QUIET_PERIOD,
+    RESUMPTION_TOKEN_TTL,
+};
+use crate::proxy_server::stream_sniffer::SNIFFED_STREAMS;
+use crate::proxy_server::tls_protocol_pack::TlsProtocolPack;
+use crate::proxy_server::udp_relay::{
+    InboundClientDatagram, UdpRelay, UdpRelayFactory, UdpRelayFactoryReal,
+};
 use crate::stream_messages::NonClandestineAttributes;
 use crate::stream_messages::RemovedStreamType;
 use crate::sub_lib::accountant::ReportExitServiceConsumedMessage;
 use crate::sub_lib::accountant::ReportRoutingServiceConsumedMessage;
 use crate::sub_lib::bidi_hashmap::BidiHashMap;
 use crate::sub_lib::cryptde::CryptDE;
+use crate::sub_lib::cryptde::PlainData;
 use crate::sub_lib::cryptde::PublicKey;
 use crate::sub_lib::dispatcher::InboundClientData;
 use crate::sub_lib::dispatcher::{Endpoint, StreamShutdownMsg};
 use crate::sub_lib::hopper::{ExpiredCoresPackage, IncipientCoresPackage};
-use crate::sub_lib::logger::Logger;
+use crate::sub_lib::logger::{with_correlation_id, Logger};
+use crate::sub_lib::metrics;
 use crate::sub_lib::neighborhood::RatePack;
 use crate::sub_lib::neighborhood::RouteQueryMessage;
 use crate::sub_lib::neighborhood::RouteQueryResponse;
 use crate::sub_lib::neighborhood::{ExpectedService, NodeRecordMetadataMessage};
 use crate::sub_lib::neighborhood::{ExpectedServices, DEFAULT_RATE_PACK};
 use crate::sub_lib::peer_actors::BindMessage;
-use crate::sub_lib::proxy_client::{ClientResponsePayload_0v1, DnsResolveFailure_0v1};
+use crate::sub_lib::proxy_client::{
+    ClientResponsePayload_0v1, ClientUdpResponsePayload_0v1, DnsResolveFailure_0v1, StreamsQuery,
+};
 use crate::sub_lib::proxy_server::ClientRequestPayload_0v1;
 use crate::sub_lib::proxy_server::ProxyServerSubs;
 use crate::sub_lib::proxy_server::{
-    AddReturnRouteMessage, AddRouteMessage, DEFAULT_MINIMUM_HOP_COUNT,
+    AddReturnRouteMessage, AddRouteMessage, ClientUdpRequestPayload_0v1, EncryptedSniFallback,
+    MultipathMode, ProxyProtocol, SocksConfig, SocksCredentials, StreamAckPayload_0v1,
+    DEFAULT_MINIMUM_HOP_COUNT,
 };
 use crate::sub_lib::route::Route;
+use crate::sub_lib::sequence_window::ReceiveWindow;
 use crate::sub_lib::set_consuming_wallet_message::SetConsumingWalletMessage;
 use crate::sub_lib::stream_handler_pool::TransmitDataMsg;
 use crate::sub_lib::stream_key::StreamKey;
+use crate::sub_lib::stream_stats::StreamStats;
 use crate::sub_lib::ttl_hashmap::TtlHashMap;
 use crate::sub_lib::utils::NODE_MAILBOX_CAPACITY;
 use crate::sub_lib::wallet::Wallet;
 use actix::Actor;
 use actix::Addr;
+use actix::AsyncContext;
 use actix::Context;
 use actix::Handler;
 use actix::Recipient;
+use masq_lib::constants::{HTTP_PORT, SMTP_SUBMISSION_PORT, TLS_PORT};
+use masq_lib::messages::{
+    FromMessageBody, ToMessageBody, UiApplicationOriginReport, UiApplicationRulesRequest,
+    UiApplicationRulesResponse, UiEncryptedSniBroadcast, UiEncryptedSniHostRequest,
+    UiEncryptedSniHostResponse, UiRouteFailureBroadcast, UiSetApplicationRulesRequest,
+    UiSetApplicationRulesResponse, UiSetSplitTunnelRulesRequest, UiSetSplitTunnelRulesResponse,
+    UiSplitTunnelRulesRequest, UiSplitTunnelRulesResponse, UiStream, UiStreamsRequest,
+    APPLICATION_RULES_ERROR, ENCRYPTED_SNI_ERROR, SPLIT_TUNNEL_RULES_ERROR,
+};
+use masq_lib::ui_gateway::MessageTarget::{AllClients, ClientId};
+use masq_lib::ui_gateway::{MessageBody, MessagePath, NodeFromUiMessage, NodeToUiMessage};
 use pretty_hex::PrettyHex;
 use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::rc::Rc;
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime};
 use tokio::prelude::Future;
 
 pub const CRASH_KEY: &str = "PROXYSERVER";
 pub const RETURN_ROUTE_TTL: Duration = Duration::from_secs(120);
+// A companion's report of which application opened a connection is good for this long
+pub const APPLICATION_ORIGIN_TTL: Duration = Duration::from_secs(60);
+// A new stream that hears nothing back for this long has probably lost a hop along its route
+pub const STREAM_REPLAY_TIMEOUT: Duration = Duration::from_secs(15);
+pub const STREAM_REPLAY_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+pub const MAX_STREAM_REPLAYS: u32 = 2;
 
 struct ProxyServerOutSubs {
     dispatcher: Recipient<TransmitDataMsg>,
@@ -65,6 +119,8 @@ struct ProxyServerOutSubs {
     add_return_route: Recipient<AddReturnRouteMessage>,
     add_route: Recipient<AddRouteMessage>,
     stream_shutdown_sub: Recipient<StreamShutdownMsg>,
+    to_ui_message_sub: Recipient<NodeToUiMessage>,
+    streams_query: Recipient<StreamsQuery>,
 }
 
 pub struct ProxyServer {
@@ -72,8 +128,19 @@ pub struct ProxyServer {
     client_request_payload_factory: ClientRequestPayloadFactory,
     stream_key_factory: Box<dyn StreamKeyFactory>,
     keys_and_addrs: BidiHashMap<StreamKey, SocketAddr>,
-    tunneled_hosts: HashMap<StreamKey, String>,
+    tunneled_hosts: HashMap<StreamKey, Host>,
     stream_key_routes: HashMap<StreamKey, RouteQueryResponse>,
+    // Disjoint routes to the same exit Nodes, for streams using multipath
+    secondary_routes: HashMap<StreamKey, RouteQueryResponse>,
+    multipath_mode: MultipathMode,
+    encrypted_sni_fallback: EncryptedSniFallback,
+    // TLS streams waiting for a UI to say where their hidden servers are
+    encrypted_sni_streams: HashMap<StreamKey, EncryptedSniStream>,
+    next_encrypted_sni_connection_id: u64,
+    receive_windows: HashMap<StreamKey, ReceiveWindow>,
+    stream_stats: HashMap<StreamKey, StreamStats>,
+    replayable_requests: HashMap<StreamKey, ReplayableRequest>,
+    stream_replay_timeout: Duration,
     is_decentralized: bool,
     consuming_wallet_balance: Option<i64>,
     main_cryptde: &'static dyn CryptDE,
@@ -81,6 +148,98 @@ pub struct ProxyServer {
     logger: Logger,
     route_ids_to_return_routes: TtlHashMap<u32, AddReturnRouteMessage>,
     browser_proxy_sequence_offset: bool,
+    socks_config_opt: Option<SocksConfig>,
+    socks_sessions: HashMap<StreamKey, SocksSession>,
+    udp_relay_factory: Box<dyn UdpRelayFactory>,
+    udp_relay_opt: Option<Box<dyn UdpRelay>>,
+    udp_associations: HashMap<StreamKey, UdpAssociation>,
+    split_tunnel_rules: SplitTunnelRules,
+    app_rules: AppRules,
+    process_identifier: Box<dyn ProcessIdentifier>,
+    reported_applications: TtlHashMap<SocketAddr, String>,
+    persistent_config_opt: Option<Box<dyn PersistentConfiguration>>,
+    resumable_streams: HashMap<StreamKey, ResumableStream>,
+    // Idle streams from before the last restart, waiting for new connections to take them over
+    resumption_tokens: Vec<ResumptionToken>,
+    resumption_store_opt: Option<Box<dyn ResumptionStore>>,
+    resumption_checkpointed: bool,
+    protocol_sniffing: bool,
+}
+
+struct SocksSession {
+    negotiation: Socks5Negotiation,
+    // The SOCKS handshake uses up sequence numbers in both directions that the exit Node never sees
+    packets_received: u64,
+    replies_sent: u64,
+}
+
+impl SocksSession {
+    fn new(
+        credentials_opt: Option<SocksCredentials>,
+        udp_relay_port_opt: Option<u16>,
+    ) -> SocksSession {
+        let negotiation = Socks5Negotiation::new(credentials_opt);
+        SocksSession {
+            negotiation: match udp_relay_port_opt {
+                Some(port) => negotiation.with_udp_relay_port(port),
+                None => negotiation,
+            },
+            packets_received: 0,
+            replies_sent: 0,
+        }
+    }
+}
+
+// The first request of a new stream, kept until anything comes back, so that it can go out again
+// over another route. Only requests that are safe to send twice are kept.
+struct ReplayableRequest {
+    msg: InboundClientData,
+    sent_at: Instant,
+    replays: u32,
+}
+
+// A plain HTTP stream, which a new connection to the same host could take over after a restart as
+// long as it's between requests
+struct ResumableStream {
+    hostname: String,
+    port: u16,
+    // What an earlier run of this Node had already used of each direction's sequence numbers
+    request_offset: u64,
+    response_offset: u64,
+    next_request_sequence: u64,
+    awaiting_response: bool,
+    last_activity: Instant,
+    // The first request after a resumption, to start over with if the exit Node lost the stream
+    resumed_request_opt: Option<InboundClientData>,
+}
+
+impl ResumableStream {
+    fn new(hostname: String, port: u16, now: Instant) -> ResumableStream {
+        ResumableStream {
+            hostname,
+            port,
+            request_offset: 0,
+            response_offset: 0,
+            next_request_sequence: 0,
+            awaiting_response: false,
+            last_activity: now,
+            resumed_request_opt: None,
+        }
+    }
+}
+
+struct EncryptedSniStream {
+    connection_id: u64,
+    client_addr: SocketAddr,
+    held: Vec<InboundClientData>,
+}
+
+// A SOCKS client's UDP ASSOCIATE lasts as long as the TCP stream that asked for it, and is
+// keyed by that stream's key. The client's UDP address isn't known until its first datagram.
+struct UdpAssociation {
+    // 0 if the client didn't say which port it would send from
+    client_port: u16,
+    client_addr_opt: Option<SocketAddr>,
 }
 
 impl Actor for ProxyServer {
@@ -102,8 +261,33 @@ impl Handler<BindMessage> for ProxyServer {
             add_return_route: msg.peer_actors.proxy_server.add_return_route,
             add_route: msg.peer_actors.proxy_server.add_route,
             stream_shutdown_sub: msg.peer_actors.proxy_server.stream_shutdown_sub,
+            to_ui_message_sub: msg.peer_actors.ui_gateway.node_to_ui_message_sub,
+            streams_query: msg.peer_actors.proxy_client.streams_query,
         };
         self.subs = Some(subs);
+        if let Some(socks_config) = self.socks_config_opt.as_ref() {
+            match self.udp_relay_factory.make(
+                socks_config.port,
+                ctx.address().recipient::<InboundClientDatagram>(),
+                self.logger.clone(),
+            ) {
+                Ok(udp_relay) => self.udp_relay_opt = Some(udp_relay),
+                Err(e) => error!(
+                    self.logger,
+                    "Could not open SOCKS UDP relay on port {}: {}; UDP ASSOCIATE will be refused",
+                    socks_config.port,
+                    e
+                ),
+            }
+        }
+        ctx.run_interval(STREAM_REPLAY_CHECK_INTERVAL, |proxy_server, _ctx| {
+            proxy_server.replay_stalled_requests(Instant::now())
+        });
+        if self.resumption_store_opt.is_some() {
+            ctx.run_interval(RESUMPTION_CHECKPOINT_INTERVAL, |proxy_server, _ctx| {
+                proxy_server.checkpoint_resumable_streams(Instant::now())
+            });
+        }
     }
 }
 
@@ -123,7 +307,9 @@ impl Handler<InboundClientData> for ProxyServer {
     type Result = ();
 
     fn handle(&mut self, msg: InboundClientData, _ctx: &mut Self::Context) -> Self::Result {
-        if msg.is_connect() {
+        if self.is_socks_data(&msg) {
+            self.handle_socks_client_data(msg);
+        } else if msg.is_connect() {
             self.tls_connect(&msg);
             self.browser_proxy_sequence_offset = true;
         } else {
@@ -160,7 +346,17 @@ impl Handler<AddRouteMessage> for ProxyServer {
     type Result = ();
 
     fn handle(&mut self, msg: AddRouteMessage, _ctx: &mut Self::Context) -> Self::Result {
+        if msg.is_secondary {
+            self.add_secondary_route(msg.stream_key, msg.route);
+            return;
+        }
         debug!(self.logger, "Establishing stream key {}", msg.stream_key);
+        if let Some(stats) = self.stream_stats.get_mut(&msg.stream_key) {
+            stats.hop_count_opt = Some(ProxyServer::hop_count(&msg.route.expected_services));
+        }
+        if self.multipath_mode != MultipathMode::Off {
+            self.request_secondary_route(msg.stream_key, &msg.route);
+        }
         self.stream_key_routes.insert(msg.stream_key, msg.route);
     }
 }
@@ -185,7 +381,29 @@ impl Handler<ExpiredCoresPackage<ClientResponsePayload_0v1>> for ProxyServer {
         msg: ExpiredCoresPackage<ClientResponsePayload_0v1>,
         _ctx: &mut Self::Context,
     ) -> Self::Result {
-        self.handle_client_response_payload(&msg)
+        with_correlation_id(msg.payload.stream_key.to_string(), || {
+            self.handle_client_response_payload(&msg)
+        })
+    }
+}
+
+impl Handler<ExpiredCoresPackage<ClientUdpResponsePayload_0v1>> for ProxyServer {
+    type Result = ();
+
+    fn handle(
+        &mut self,
+        msg: ExpiredCoresPackage<ClientUdpResponsePayload_0v1>,
+        _ctx: &mut Self::Context,
+    ) -> Self::Result {
+        self.handle_client_udp_response_payload(&msg)
+    }
+}
+
+impl Handler<InboundClientDatagram> for ProxyServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: InboundClientDatagram, _ctx: &mut Self::Context) -> Self::Result {
+        self.handle_client_datagram(msg)
     }
 }
 
@@ -197,12 +415,40 @@ impl Handler<StreamShutdownMsg> for ProxyServer {
     }
 }
 
+impl Handler<NodeFromUiMessage> for ProxyServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: NodeFromUiMessage, _ctx: &mut Self::Context) -> Self::Result {
+        let client_id = msg.client_id;
+        if let Ok((_, context_id)) = UiSplitTunnelRulesRequest::fmb(msg.body.clone()) {
+            self.handle_split_tunnel_rules_request(client_id, context_id);
+        } else if let Ok((request, context_id)) =
+            UiSetSplitTunnelRulesRequest::fmb(msg.body.clone())
+        {
+            self.handle_set_split_tunnel_rules_request(client_id, context_id, request);
+        } else if let Ok((request, context_id)) = UiEncryptedSniHostRequest::fmb(msg.body.clone()) {
+            self.handle_encrypted_sni_host_request(client_id, context_id, request);
+        } else if let Ok((_, context_id)) = UiApplicationRulesRequest::fmb(msg.body.clone()) {
+            self.handle_application_rules_request(client_id, context_id);
+        } else if let Ok((request, context_id)) =
+            UiSetApplicationRulesRequest::fmb(msg.body.clone())
+        {
+            self.handle_set_application_rules_request(client_id, context_id, request);
+        } else if let Ok((report, _)) = UiApplicationOriginReport::fmb(msg.body.clone()) {
+            self.handle_application_origin_report(report);
+        } else if let Ok((_, context_id)) = UiStreamsRequest::fmb(msg.body) {
+            self.handle_streams_request(client_id, context_id);
+        }
+    }
+}
+
 impl ProxyServer {
     pub fn new(
         main_cryptde: &'static dyn CryptDE,
         alias_cryptde: &'static dyn CryptDE,
         is_decentralized: bool,
         consuming_wallet_balance: Option<i64>,
+        socks_config_opt: Option<SocksConfig>,
     ) -> ProxyServer {
         ProxyServer {
             subs: None,
@@ -211,6 +457,15 @@ impl ProxyServer {
             keys_and_addrs: BidiHashMap::new(),
             tunneled_hosts: HashMap::new(),
             stream_key_routes: HashMap::new(),
+            secondary_routes: HashMap::new(),
+            multipath_mode: MultipathMode::Off,
+            encrypted_sni_fallback: EncryptedSniFallback::Refuse,
+            encrypted_sni_streams: HashMap::new(),
+            next_encrypted_sni_connection_id: 1,
+            receive_windows: HashMap::new(),
+            stream_stats: HashMap::new(),
+            replayable_requests: HashMap::new(),
+            stream_replay_timeout: STREAM_REPLAY_TIMEOUT,
             is_decentralized,
             consuming_wallet_balance,
             main_cryptde,
@@ -218,7 +473,93 @@ impl ProxyServer {
             logger: Logger::new("ProxyServer"),
             route_ids_to_return_routes: TtlHashMap::new(RETURN_ROUTE_TTL),
             browser_proxy_sequence_offset: false,
+            socks_config_opt,
+            socks_sessions: HashMap::new(),
+            udp_relay_factory: Box::new(UdpRelayFactoryReal {}),
+            udp_relay_opt: None,
+            udp_associations: HashMap::new(),
+            split_tunnel_rules: SplitTunnelRules::default(),
+            app_rules: AppRules::default(),
+            process_identifier: Box::new(ProcessIdentifierReal {}),
+            reported_applications: TtlHashMap::new(APPLICATION_ORIGIN_TTL),
+            persistent_config_opt: None,
+            resumable_streams: HashMap::new(),
+            resumption_tokens: vec![],
+            resumption_store_opt: None,
+            resumption_checkpointed: false,
+            protocol_sniffing: false,
+        }
+    }
+
+    pub fn set_multipath_mode(&mut self, multipath_mode: MultipathMode) {
+        self.multipath_mode = multipath_mode;
+    }
+
+    pub fn set_protocol_sniffing(&mut self, protocol_sniffing: bool) {
+        self.protocol_sniffing = protocol_sniffing;
+    }
+
+    pub fn set_encrypted_sni_fallback(&mut self, encrypted_sni_fallback: EncryptedSniFallback) {
+        self.encrypted_sni_fallback = encrypted_sni_fallback;
+    }
+
+    pub fn load_split_tunnel_rules(&mut self, persistent_config: Box<dyn PersistentConfiguration>) {
+        self.split_tunnel_rules = match persistent_config.split_tunnel_rules() {
+            Ok(Some(rules_str)) => match rules_str.parse::<SplitTunnelRules>() {
+                Ok(rules) => rules,
+                Err(e) => panic!(
+                    "Database corrupt: invalid split-tunnel rules '{}': {}",
+                    rules_str, e
+                ),
+            },
+            Ok(None) => SplitTunnelRules::default(),
+            Err(e) => panic!("Could not read split-tunnel rules from database: {:?}", e),
+        };
+        if !self.split_tunnel_rules.is_empty() {
+            info!(
+                self.logger,
+                "Split tunneling is on: only traffic to {} will go through MASQ",
+                self.split_tunnel_rules
+            );
+        }
+        self.persistent_config_opt = Some(persistent_config);
+    }
+
+    pub fn load_app_rules(&mut self, persistent_config: &dyn PersistentConfiguration) {
+        self.app_rules = match persistent_config.application_rules() {
+            Ok(Some(rules_str)) => match rules_str.parse::<AppRules>() {
+                Ok(rules) => rules,
+                Err(e) => panic!(
+                    "Database corrupt: invalid application rules '{}': {}",
+                    rules_str, e
+                ),
+            },
+            Ok(None) => AppRules::default(),
+            Err(e) => panic!("Could not read application rules from database: {:?}", e),
+        };
+        if !self.app_rules.is_empty() {
+            info!(self.logger, "Application rules: {}", self.app_rules);
+        }
+    }
+
+    // Tokens are good for one run only: the exit Nodes' sequence numbers move on once a stream is
+    // taken over, so the file is emptied as soon as it's read
+    pub fn load_resumption_tokens(&mut self, store: Box<dyn ResumptionStore>) {
+        let now = now_time_t();
+        self.resumption_tokens = store
+            .load()
+            .into_iter()
+            .filter(|token| token.expires_at > now)
+            .collect();
+        store.save(&[]);
+        if !self.resumption_tokens.is_empty() {
+            info!(
+                self.logger,
+                "{} idle stream(s) from before the restart can be resumed",
+                self.resumption_tokens.len()
+            );
         }
+        self.resumption_store_opt = Some(store);
     }
 
     pub fn make_subs_from(addr: &Addr<ProxyServer>) -> ProxyServerSubs {
@@ -231,11 +572,348 @@ impl ProxyServer {
             dns_failure_from_hopper: addr
                 .clone()
                 .recipient::<ExpiredCoresPackage<DnsResolveFailure_0v1>>(),
+            udp_from_hopper: addr
+                .clone()
+                .recipient::<ExpiredCoresPackage<ClientUdpResponsePayload_0v1>>(),
             add_return_route: addr.clone().recipient::<AddReturnRouteMessage>(),
             add_route: addr.clone().recipient::<AddRouteMessage>(),
             stream_shutdown_sub: addr.clone().recipient::<StreamShutdownMsg>(),
             set_consuming_wallet_sub: addr.clone().recipient::<SetConsumingWalletMessage>(),
+            from_ui_message_sub: addr.clone().recipient::<NodeFromUiMessage>(),
+        }
+    }
+
+    fn handle_split_tunnel_rules_request(&mut self, client_id: u64, context_id: u64) {
+        let response = UiSplitTunnelRulesResponse {
+            rules: self.split_tunnel_rules.to_strings(),
+        };
+        self.send_to_ui(client_id, response.tmb(context_id));
+    }
+
+    fn handle_set_split_tunnel_rules_request(
+        &mut self,
+        client_id: u64,
+        context_id: u64,
+        request: UiSetSplitTunnelRulesRequest,
+    ) {
+        let body = match self.set_split_tunnel_rules(&request.rules) {
+            Ok(()) => UiSetSplitTunnelRulesResponse {}.tmb(context_id),
+            Err(message) => MessageBody {
+                opcode: "setSplitTunnelRules".to_string(),
+                path: MessagePath::Conversation(context_id),
+                payload: Err((SPLIT_TUNNEL_RULES_ERROR, message)),
+            },
+        };
+        self.send_to_ui(client_id, body);
+    }
+
+    fn set_split_tunnel_rules(&mut self, rule_strs: &[String]) -> Result<(), String> {
+        let rules = SplitTunnelRules::from_strs(rule_strs)?;
+        let rules_opt = if rules.is_empty() {
+            None
+        } else {
+            Some(rules.to_string())
+        };
+        if let Some(persistent_config) = self.persistent_config_opt.as_mut() {
+            persistent_config
+                .set_split_tunnel_rules(rules_opt)
+                .map_err(|e| format!("Could not save split-tunnel rules: {:?}", e))?;
+        }
+        if rules.is_empty() {
+            info!(self.logger, "Split tunneling is off");
+        } else {
+            info!(
+                self.logger,
+                "Split tunneling is on: only traffic to {} will go through MASQ", rules
+            );
+        }
+        self.split_tunnel_rules = rules;
+        Ok(())
+    }
+
+    // The ProxyClient adds the streams it's serving and answers the UI
+    fn handle_streams_request(&self, client_id: u64, context_id: u64) {
+        let now = Instant::now();
+        let mut consumed = self.stream_stats.iter().collect::<Vec<_>>();
+        consumed.sort_by_key(|(_, stats)| stats.opened_at);
+        let consuming = consumed
+            .into_iter()
+            .map(|(stream_key, stats)| stats.to_ui_stream(stream_key, now))
+            .collect::<Vec<UiStream>>();
+        self.out_subs("ProxyClient")
+            .streams_query
+            .try_send(StreamsQuery {
+                client_id,
+                context_id,
+                consuming,
+            })
+            .expect("ProxyClient is dead");
+    }
+
+    // Relays and the exit Node, but not this Node, which originates the stream
+    fn hop_count(expected_services: &ExpectedServices) -> usize {
+        let over = match expected_services {
+            ExpectedServices::OneWay(over) => over,
+            ExpectedServices::RoundTrip(over, _, _) => over,
+        };
+        over.iter()
+            .filter(|service| **service != ExpectedService::Nothing)
+            .count()
+    }
+
+    fn handle_application_rules_request(&mut self, client_id: u64, context_id: u64) {
+        let response = UiApplicationRulesResponse {
+            rules: self.app_rules.to_strings(),
+        };
+        self.send_to_ui(client_id, response.tmb(context_id));
+    }
+
+    fn handle_set_application_rules_request(
+        &mut self,
+        client_id: u64,
+        context_id: u64,
+        request: UiSetApplicationRulesRequest,
+    ) {
+        let body = match self.set_app_rules(&request.rules) {
+            Ok(()) => UiSetApplicationRulesResponse {}.tmb(context_id),
+            Err(message) => MessageBody {
+                opcode: "setApplicationRules".to_string(),
+                path: MessagePath::Conversation(context_id),
+                payload: Err((APPLICATION_RULES_ERROR, message)),
+            },
+        };
+        self.send_to_ui(client_id, body);
+    }
+
+    fn set_app_rules(&mut self, rule_strs: &[String]) -> Result<(), String> {
+        let rules = AppRules::from_strs(rule_strs)?;
+        let rules_opt = if rules.is_empty() {
+            None
+        } else {
+            Some(rules.to_string())
+        };
+        if let Some(persistent_config) = self.persistent_config_opt.as_mut() {
+            persistent_config
+                .set_application_rules(rules_opt)
+                .map_err(|e| format!("Could not save application rules: {:?}", e))?;
+        }
+        if rules.is_empty() {
+            info!(self.logger, "Application rules are off");
+        } else {
+            info!(self.logger, "Application rules: {}", rules);
         }
+        self.app_rules = rules;
+        Ok(())
+    }
+
+    fn handle_application_origin_report(&mut self, report: UiApplicationOriginReport) {
+        match report.client_address.parse::<SocketAddr>() {
+            Ok(client_addr) => {
+                debug!(
+                    self.logger,
+                    "Connections from {} belong to {}", client_addr, report.application
+                );
+                self.reported_applications
+                    .insert(client_addr, report.application);
+            }
+            Err(_) => warning!(
+                self.logger,
+                "Ignoring application origin for unparseable client address '{}'",
+                report.client_address
+            ),
+        }
+    }
+
+    // Reported origins take precedence over what can be found out locally, and nobody looks
+    // unless there are rules to apply
+    fn app_action(&self, client_addr: SocketAddr) -> Option<AppAction> {
+        if self.app_rules.is_empty() {
+            return None;
+        }
+        let application = match self.reported_applications.get(&client_addr) {
+            Some(application) => application.as_ref().clone(),
+            None => self.process_identifier.application(client_addr)?,
+        };
+        let action_opt = self.app_rules.action_for(&application);
+        if let Some(action) = action_opt {
+            debug!(
+                self.logger,
+                "Connection from {} belongs to {}: {}", client_addr, application, action
+            );
+        }
+        action_opt
+    }
+
+    fn handle_encrypted_sni_host_request(
+        &mut self,
+        client_id: u64,
+        context_id: u64,
+        request: UiEncryptedSniHostRequest,
+    ) {
+        let connection_id = request.connection_id;
+        let host_name_opt = match request.host_name_opt {
+            None => Ok(None),
+            Some(host_name) => match host_name.parse::<EncryptedSniFallback>() {
+                Ok(EncryptedSniFallback::Host(host_name)) => Ok(Some(host_name)),
+                _ => Err(format!("'{}' is not a host name", host_name)),
+            },
+        };
+        let stream_key_opt = self
+            .encrypted_sni_streams
+            .iter()
+            .find(|(_, stream)| stream.connection_id == connection_id)
+            .map(|(stream_key, _)| *stream_key);
+        let result = match (stream_key_opt, host_name_opt) {
+            (None, _) => Err(format!(
+                "No TLS connection {} is waiting for a server",
+                connection_id
+            )),
+            (Some(_), Err(message)) => Err(message),
+            (Some(stream_key), Ok(host_name_opt)) => Ok((stream_key, host_name_opt)),
+        };
+        let body = match result {
+            Ok(_) => UiEncryptedSniHostResponse {}.tmb(context_id),
+            Err(ref message) => MessageBody {
+                opcode: "encryptedSniHost".to_string(),
+                path: MessagePath::Conversation(context_id),
+                payload: Err((ENCRYPTED_SNI_ERROR, message.clone())),
+            },
+        };
+        self.send_to_ui(client_id, body);
+        if let Ok((stream_key, host_name_opt)) = result {
+            let stream = self
+                .encrypted_sni_streams
+                .remove(&stream_key)
+                .expect("Encrypted-SNI stream disappeared");
+            match host_name_opt {
+                Some(host_name) => {
+                    info!(
+                        self.logger,
+                        "Sending TLS connection {} from {} to {}",
+                        stream.connection_id,
+                        stream.client_addr,
+                        host_name
+                    );
+                    self.tunneled_hosts.insert(
+                        stream_key,
+                        Host {
+                            name: host_name,
+                            port: None,
+                        },
+                    );
+                    stream
+                        .held
+                        .into_iter()
+                        .for_each(|msg| self.handle_normal_client_data(msg, false));
+                }
+                None => self.refuse_encrypted_sni(stream.client_addr),
+            }
+        }
+    }
+
+    // A client hello that hides the name of its server can't go anywhere until something else
+    // names the server. Returns the data if it should carry on as usual.
+    fn screen_for_encrypted_sni(
+        &mut self,
+        msg: InboundClientData,
+        stream_key: &StreamKey,
+    ) -> Option<InboundClientData> {
+        if let Some(stream) = self.encrypted_sni_streams.get_mut(stream_key) {
+            stream.held.push(msg);
+            return None;
+        }
+        if self.stream_key_routes.contains_key(stream_key)
+            || self.tunneled_hosts.contains_key(stream_key)
+            || from_ibcd(&msg, &self.logger).map(|pack| pack.proxy_protocol())
+                != Some(ProxyProtocol::TLS)
+            || !TlsProtocolPack::hides_host(&PlainData::new(&msg.data))
+        {
+            return Some(msg);
+        }
+        match self.encrypted_sni_fallback.clone() {
+            EncryptedSniFallback::Refuse => {
+                self.refuse_encrypted_sni(msg.peer_addr);
+                None
+            }
+            EncryptedSniFallback::Prompt => {
+                let connection_id = self.next_encrypted_sni_connection_id;
+                self.next_encrypted_sni_connection_id += 1;
+                info!(
+                    self.logger,
+                    "TLS client at {} hid its server's name; waiting for a UI to name it for connection {}",
+                    msg.peer_addr,
+                    connection_id
+                );
+                let broadcast = UiEncryptedSniBroadcast {
+                    connection_id,
+                    client_address: msg.peer_addr.to_string(),
+                };
+                self.out_subs("UiGateway")
+                    .to_ui_message_sub
+                    .try_send(NodeToUiMessage {
+                        target: AllClients,
+                        body: broadcast.tmb(0),
+                    })
+                    .expect("UiGateway is dead");
+                self.encrypted_sni_streams.insert(
+                    *stream_key,
+                    EncryptedSniStream {
+                        connection_id,
+                        client_addr: msg.peer_addr,
+                        held: vec![msg],
+                    },
+                );
+                None
+            }
+            EncryptedSniFallback::Host(host_name) => {
+                info!(
+                    self.logger,
+                    "TLS client at {} hid its server's name; sending it to {}",
+                    msg.peer_addr,
+                    host_name
+                );
+                self.tunneled_hosts.insert(
+                    *stream_key,
+                    Host {
+                        name: host_name,
+                        port: None,
+                    },
+                );
+                Some(msg)
+            }
+        }
+    }
+
+    fn refuse_encrypted_sni(&self, client_addr: SocketAddr) {
+        warning!(
+            self.logger,
+            "Refusing TLS connection from {}: its server's name is encrypted",
+            client_addr
+        );
+        // TLS doesn't care whose key it is
+        let data = TlsProtocolPack {}
+            .server_impersonator()
+            .dns_resolution_failure_response(self.main_cryptde.public_key(), None);
+        self.out_subs("Dispatcher")
+            .dispatcher
+            .try_send(TransmitDataMsg {
+                endpoint: Endpoint::Socket(client_addr),
+                last_data: true,
+                sequence_number: Some(0),
+                data,
+                relayed_from_opt: None,
+            })
+            .expect("Dispatcher is dead");
+    }
+
+    fn send_to_ui(&self, client_id: u64, body: MessageBody) {
+        self.out_subs("UiGateway")
+            .to_ui_message_sub
+            .try_send(NodeToUiMessage {
+                target: ClientId(client_id),
+                body,
+            })
+            .expect("UiGateway is dead");
     }
 
     fn handle_dns_resolve_failure(&mut self, msg: &ExpiredCoresPackage<DnsResolveFailure_0v1>) {
@@ -282,13 +960,14 @@ impl ProxyServer {
                     .try_send(TransmitDataMsg {
                         endpoint: Endpoint::Socket(socket_addr),
                         last_data: true,
-                        sequence_number: Some(0), // DNS resolution errors always happen on the first request
+                        sequence_number: Some(self.socks_replies_sent(&response.stream_key)), // DNS resolution errors always happen on the first request
                         data: from_protocol(return_route_info.protocol)
                             .server_impersonator()
                             .dns_resolution_failure_response(
                                 &exit_public_key,
                                 return_route_info.server_name.clone(),
                             ),
+                        relayed_from_opt: None,
                     })
                     .expect("Dispatcher is dead");
                 debug!(
@@ -334,16 +1013,65 @@ impl ProxyServer {
         };
         match self.keys_and_addrs.a_to_b(&response.stream_key) {
             Some(socket_addr) => {
+                self.replayable_requests.remove(&response.stream_key);
+                let refused = response.sequenced_packet.last_data
+                    && response.sequenced_packet.data.is_empty()
+                    && self
+                        .resumable_streams
+                        .get(&response.stream_key)
+                        .map(|stream| stream.resumed_request_opt.is_some())
+                        .unwrap_or(false);
+                if refused {
+                    self.restart_refused_resumption(&response.stream_key);
+                    return;
+                }
+                let reception = self
+                    .receive_windows
+                    .entry(response.stream_key)
+                    .or_insert_with(ReceiveWindow::new)
+                    .receive(
+                        response.sequenced_packet.sequence_number,
+                        response.sequenced_packet.last_data,
+                    );
+                if let Some(next_sequence_number) = reception.ack_opt {
+                    self.acknowledge_response(&response.stream_key, next_sequence_number);
+                }
+                if !reception.is_new {
+                    debug!(
+                        self.logger,
+                        "Discarding retransmitted packet {} of stream {}: already relayed",
+                        response.sequenced_packet.sequence_number,
+                        response.stream_key
+                    );
+                    return;
+                }
                 self.report_response_services_consumed(
                     &return_route_info,
                     response.sequenced_packet.data.len(),
                     payload_data_len,
                 );
+                if let Some(stats) = self.stream_stats.get_mut(&response.stream_key) {
+                    stats.bytes_in += response.sequenced_packet.data.len() as u64;
+                }
+                // The browser's connection counts from zero, even if the stream didn't
+                let response_offset = match self.resumable_streams.get_mut(&response.stream_key) {
+                    Some(stream) => {
+                        stream.awaiting_response = false;
+                        stream.last_activity = Instant::now();
+                        stream.resumed_request_opt = None;
+                        stream.response_offset
+                    }
+                    None => 0,
+                };
 
                 let last_data = response.sequenced_packet.last_data;
+                let sequence_offset = if self.socks_sessions.contains_key(&response.stream_key) {
+                    self.socks_replies_sent(&response.stream_key)
+                } else {
+                    self.browser_proxy_sequence_offset as u64
+                };
                 let sequence_number = Some(
-                    response.sequenced_packet.sequence_number
-                        + self.browser_proxy_sequence_offset as u64,
+                    response.sequenced_packet.sequence_number + sequence_offset - response_offset,
                 );
                 self
                     .subs
@@ -355,9 +1083,15 @@ impl ProxyServer {
                         last_data,
                         sequence_number,
                         data: response.sequenced_packet.data.clone(),
+                        relayed_from_opt: None,
                     })
                     .expect("Dispatcher is dead");
-                if last_data {
+                let is_complete = self
+                    .receive_windows
+                    .get(&response.stream_key)
+                    .map(|window| window.is_complete())
+                    .unwrap_or(false);
+                if is_complete {
                     debug!(self.logger, "Retiring stream key {}: no more data", &response.stream_key);
                     self.purge_stream_key(&response.stream_key);
                 }
@@ -372,12 +1106,68 @@ impl ProxyServer {
         }
     }
 
+    // Acknowledgments travel the stream's own route to the exit Node, like requests do
+    fn acknowledge_response(&self, stream_key: &StreamKey, next_sequence_number: u64) {
+        let (route, over) = match self.stream_key_routes.get(stream_key) {
+            Some(RouteQueryResponse {
+                route,
+                expected_services: ExpectedServices::RoundTrip(over, _, _),
+            }) => (route, over),
+            _ => {
+                debug!(
+                    self.logger,
+                    "Can't acknowledge packets of stream {}: no route to its exit Node", stream_key
+                );
+                return;
+            }
+        };
+        let payload_destination_key =
+            match ProxyServer::payload_destination_key(over, self.alias_cryptde.public_key()) {
+                Some(key) => key,
+                None => {
+                    debug!(
+                        self.logger,
+                        "Can't acknowledge packets of stream {}: no exit Node in its route",
+                        stream_key
+                    );
+                    return;
+                }
+            };
+        let payload = StreamAckPayload_0v1 {
+            stream_key: *stream_key,
+            next_sequence_number,
+        };
+        let pkg = IncipientCoresPackage::new(
+            self.main_cryptde,
+            route.clone(),
+            payload.into(),
+            &payload_destination_key,
+        )
+        .expect("Key magically disappeared");
+        debug!(
+            self.logger,
+            "Acknowledging packets of stream {} before sequence {}",
+            stream_key,
+            next_sequence_number
+        );
+        ProxyServer::report_routing_service(
+            &self.out_subs("Accountant").accountant_routing,
+            over.clone(),
+            pkg.payload.len(),
+            &self.logger,
+        );
+        self.out_subs("Hopper")
+            .hopper
+            .try_send(pkg)
+            .expect("Hopper is dead");
+    }
+
     fn tls_connect(&mut self, msg: &InboundClientData) {
         let http_data = HttpProtocolPack {}.find_host(&msg.data.clone().into());
         match http_data {
             Some(ref host) if host.port == Some(443) => {
                 let stream_key = self.make_stream_key(&msg);
-                self.tunneled_hosts.insert(stream_key, host.name.clone());
+                self.tunneled_hosts.insert(stream_key, host.clone());
                 self.subs
                     .as_ref()
                     .expect("Dispatcher unbound in ProxyServer")
@@ -387,6 +1177,7 @@ impl ProxyServer {
                         last_data: false,
                         sequence_number: msg.sequence_number,
                         data: b"HTTP/1.1 200 OK\r\n\r\n".to_vec(),
+                        relayed_from_opt: None,
                     })
                     .expect("Dispatcher is dead");
             }
@@ -400,41 +1191,157 @@ impl ProxyServer {
                         last_data: true,
                         sequence_number: msg.sequence_number,
                         data: b"HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\n\r\n".to_vec(),
+                        relayed_from_opt: None,
                     })
                     .expect("Dispatcher is dead");
             }
         }
     }
 
-    fn out_subs(&self, actor_name: &str) -> &ProxyServerOutSubs {
-        self.subs
-            .as_ref()
-            .unwrap_or_else(|| panic!("{} unbound in ProxyServer", actor_name))
+    fn is_socks_data(&self, msg: &InboundClientData) -> bool {
+        match (&self.socks_config_opt, msg.reception_port) {
+            (Some(socks_config), Some(reception_port)) => socks_config.port == reception_port,
+            _ => false,
+        }
     }
 
-    fn handle_normal_client_data(&mut self, msg: InboundClientData, retire_stream_key: bool) {
-        let route_source = self.out_subs("Neighborhood").route_source.clone();
-        let hopper = self.out_subs("Hopper").hopper.clone();
-        let accountant_exit_sub = self.out_subs("Accountant").accountant_exit.clone();
-        let accountant_routing_sub = self.out_subs("Accountant").accountant_routing.clone();
-        let dispatcher = self.out_subs("Dispatcher").dispatcher.clone();
-        let add_return_route_sub = self.out_subs("ProxyServer").add_return_route.clone();
-        let add_route_sub = self.out_subs("ProxyServer").add_route.clone();
-        let stream_shutdown_sub = self.out_subs("ProxyServer").stream_shutdown_sub.clone();
-        let source_addr = msg.peer_addr;
-        if self.consuming_wallet_balance.is_none() && self.is_decentralized {
-            let protocol_pack = match from_ibcd(&msg, &self.logger) {
-                None => return,
-                Some(pp) => pp,
-            };
-            let data = protocol_pack
-                .server_impersonator()
-                .consuming_wallet_absent();
-            let msg = TransmitDataMsg {
-                endpoint: Endpoint::Socket(source_addr),
-                last_data: true,
+    fn handle_socks_client_data(&mut self, msg: InboundClientData) {
+        let stream_key = self.make_stream_key(&msg);
+        let credentials_opt = self
+            .socks_config_opt
+            .as_ref()
+            .and_then(|socks_config| socks_config.credentials_opt.clone());
+        let udp_relay_port_opt = match self.udp_relay_opt {
+            Some(_) => self.socks_config_opt.as_ref().map(|config| config.port),
+            None => None,
+        };
+        if self.udp_associations.contains_key(&stream_key) {
+            debug!(
+                self.logger,
+                "Ignoring {} bytes from SOCKS client {} on its UDP ASSOCIATE stream",
+                msg.data.len(),
+                msg.peer_addr
+            );
+            return;
+        }
+        let session = self
+            .socks_sessions
+            .entry(stream_key)
+            .or_insert_with(|| SocksSession::new(credentials_opt, udp_relay_port_opt));
+        if session.negotiation.is_finished() {
+            return self.handle_normal_client_data(msg, false);
+        }
+        session.packets_received += 1;
+        let mut opening_ibcd_opt = None;
+        let (reply, last_data) = match session.negotiation.add_data(&msg.data) {
+            Socks5Outcome::Negotiating(reply) => (reply, false),
+            Socks5Outcome::Connected(reply, host, early_data) => {
+                debug!(
+                    self.logger,
+                    "SOCKS client {} wants a tunnel to {}:{}",
+                    msg.peer_addr,
+                    host.name,
+                    host.port.unwrap_or(TLS_PORT)
+                );
+                let server_speaks_first = from_standard_port(Self::tunnel_reception_port(&host))
+                    .map(|protocol_pack| protocol_pack.server_speaks_first())
+                    .unwrap_or(false);
+                if server_speaks_first || !early_data.is_empty() {
+                    // Either the client won't say anything until the server does, or it already
+                    // has: the stream opens now, with whatever came in behind the request, in the
+                    // sequence slot of this last negotiation packet
+                    session.packets_received -= 1;
+                    opening_ibcd_opt = Some(InboundClientData {
+                        data: early_data,
+                        ..msg.clone()
+                    });
+                }
+                self.tunneled_hosts.insert(stream_key, host);
+                (reply, false)
+            }
+            Socks5Outcome::Associated(reply, client_port) => {
+                debug!(
+                    self.logger,
+                    "SOCKS client {} wants to relay UDP datagrams from port {}",
+                    msg.peer_addr,
+                    client_port
+                );
+                self.udp_associations.insert(
+                    stream_key,
+                    UdpAssociation {
+                        client_port,
+                        client_addr_opt: None,
+                    },
+                );
+                (reply, false)
+            }
+            Socks5Outcome::Refused(reply) => {
+                warning!(
+                    self.logger,
+                    "Refused SOCKS negotiation with {}",
+                    msg.peer_addr
+                );
+                (reply, true)
+            }
+        };
+        if reply.is_empty() {
+            return;
+        }
+        let sequence_number = session.replies_sent;
+        session.replies_sent += 1;
+        self.out_subs("Dispatcher")
+            .dispatcher
+            .try_send(TransmitDataMsg {
+                endpoint: Endpoint::Socket(msg.peer_addr),
+                last_data,
+                sequence_number: Some(sequence_number),
+                data: reply,
+                relayed_from_opt: None,
+            })
+            .expect("Dispatcher is dead");
+        if let Some(opening_ibcd) = opening_ibcd_opt {
+            self.handle_normal_client_data(opening_ibcd, false);
+        }
+    }
+
+    fn socks_replies_sent(&self, stream_key: &StreamKey) -> u64 {
+        self.socks_sessions
+            .get(stream_key)
+            .map(|session| session.replies_sent)
+            .unwrap_or(0)
+    }
+
+    fn out_subs(&self, actor_name: &str) -> &ProxyServerOutSubs {
+        self.subs
+            .as_ref()
+            .unwrap_or_else(|| panic!("{} unbound in ProxyServer", actor_name))
+    }
+
+    fn handle_normal_client_data(&mut self, msg: InboundClientData, retire_stream_key: bool) {
+        let route_source = self.out_subs("Neighborhood").route_source.clone();
+        let hopper = self.out_subs("Hopper").hopper.clone();
+        let accountant_exit_sub = self.out_subs("Accountant").accountant_exit.clone();
+        let accountant_routing_sub = self.out_subs("Accountant").accountant_routing.clone();
+        let dispatcher = self.out_subs("Dispatcher").dispatcher.clone();
+        let add_return_route_sub = self.out_subs("ProxyServer").add_return_route.clone();
+        let add_route_sub = self.out_subs("ProxyServer").add_route.clone();
+        let stream_shutdown_sub = self.out_subs("ProxyServer").stream_shutdown_sub.clone();
+        let to_ui_message_sub = self.out_subs("UiGateway").to_ui_message_sub.clone();
+        let source_addr = msg.peer_addr;
+        if self.consuming_wallet_balance.is_none() && self.is_decentralized {
+            let protocol_pack = match from_ibcd(&msg, &self.logger) {
+                None => return,
+                Some(pp) => pp,
+            };
+            let data = protocol_pack
+                .server_impersonator()
+                .consuming_wallet_absent();
+            let msg = TransmitDataMsg {
+                endpoint: Endpoint::Socket(source_addr),
+                last_data: true,
                 sequence_number: Some(0),
                 data,
+                relayed_from_opt: None,
             };
             dispatcher.try_send(msg).expect("Dispatcher is dead");
             error!(
@@ -444,18 +1351,83 @@ impl ProxyServer {
             return;
         }
         let stream_key = self.make_stream_key(&msg);
-        let payload = match self.make_payload(msg, &stream_key) {
+        let msg = match self.screen_for_encrypted_sni(msg, &stream_key) {
+            Some(msg) => msg,
+            None => return,
+        };
+        let app_action_opt = if self.stream_key_routes.contains_key(&stream_key) {
+            None
+        } else {
+            self.app_action(source_addr)
+        };
+        if app_action_opt == Some(AppAction::Block) {
+            info!(
+                self.logger,
+                "Closing connection from {}: its application is blocked", source_addr
+            );
+            dispatcher
+                .try_send(TransmitDataMsg {
+                    endpoint: Endpoint::Socket(source_addr),
+                    last_data: true,
+                    sequence_number: Some(0),
+                    data: vec![],
+                    relayed_from_opt: None,
+                })
+                .expect("Dispatcher is dead");
+            return;
+        }
+        // Once a second request has gone out, the stream can't simply start over
+        let replayable_opt = if self.replayable_requests.remove(&stream_key).is_none()
+            && !self.stream_key_routes.contains_key(&stream_key)
+            && self.is_replayable(&msg, &stream_key)
+        {
+            Some(msg.clone())
+        } else {
+            None
+        };
+        let resumable_msg_opt = if self.may_resume(&msg, &stream_key) {
+            Some(msg.clone())
+        } else {
+            None
+        };
+        if self.protocol_sniffing {
+            self.sniff_if_unrecognized(&msg, &stream_key);
+        }
+        let mut payload = match self.make_payload(msg, &stream_key) {
             Ok(payload) => payload,
             Err(_e) => {
                 return;
             }
         };
-        let logger = self.logger.clone();
-        let minimum_hop_count = if self.is_decentralized {
-            DEFAULT_MINIMUM_HOP_COUNT
-        } else {
-            0
+        let resumed_token_opt = match resumable_msg_opt {
+            Some(msg) => self.resume_stream(stream_key, &mut payload, msg),
+            None => None,
         };
+        let stream_key = payload.stream_key;
+        self.track_resumable_stream(&mut payload);
+        if let Some(msg) = replayable_opt {
+            self.replayable_requests.insert(
+                stream_key,
+                ReplayableRequest {
+                    msg,
+                    sent_at: Instant::now(),
+                    replays: 0,
+                },
+            );
+        }
+        self.stream_stats
+            .entry(stream_key)
+            .or_insert_with(|| {
+                StreamStats::new(
+                    payload.protocol,
+                    payload.target_hostname.clone(),
+                    Instant::now(),
+                )
+            })
+            .bytes_out += payload.sequenced_packet.data.len() as u64;
+        let logger = self.logger.clone();
+        let minimum_hop_count =
+            self.minimum_hop_count(payload.target_hostname.as_deref(), app_action_opt);
         let cryptde = self.main_cryptde.dup();
         match self.stream_key_routes.get(&stream_key) {
             Some(route_query_response) => {
@@ -466,10 +1438,33 @@ impl ProxyServer {
                     payload.sequenced_packet.sequence_number,
                     payload.sequenced_packet.data.len()
                 );
+                let mut routes = self.outbound_routes(
+                    &stream_key,
+                    payload.sequenced_packet.sequence_number,
+                    route_query_response,
+                );
+                let last_route = routes.pop().expect("No route for existing stream");
+                // Only the last copy of a packet may retire the stream key
+                routes.into_iter().for_each(|route| {
+                    ProxyServer::try_transmit_to_hopper(
+                        cryptde.dup(),
+                        &hopper,
+                        route,
+                        payload.clone(),
+                        logger.clone(),
+                        source_addr,
+                        &dispatcher,
+                        &accountant_exit_sub,
+                        &accountant_routing_sub,
+                        &add_return_route_sub,
+                        None,
+                    )
+                    .expect("Could not transmit to hopper");
+                });
                 ProxyServer::try_transmit_to_hopper(
                     cryptde,
                     &hopper,
-                    route_query_response.clone(),
+                    last_route,
                     payload,
                     logger,
                     source_addr,
@@ -490,11 +1485,17 @@ impl ProxyServer {
                     "Getting route and opening new stream with key {} to transmit: sequence {}, length {}",
                     stream_key, payload.sequenced_packet.sequence_number, payload.sequenced_packet.data.len()
                 );
+                let route_query_message =
+                    RouteQueryMessage::data_indefinite_route_request(minimum_hop_count)
+                        .with_exit_target(payload.target_hostname.clone(), payload.target_port);
+                // A resumed stream has to go back to the exit Node that has it
+                let route_query_message = match resumed_token_opt {
+                    Some(token) => route_query_message.with_target_key(token.exit_key),
+                    None => route_query_message,
+                };
                 tokio::spawn(
                     route_source
-                        .send(RouteQueryMessage::data_indefinite_route_request(
-                            minimum_hop_count,
-                        ))
+                        .send(route_query_message)
                         .then(move |route_result| {
                             match route_result {
                                 Ok(Some(route_query_response)) => {
@@ -502,6 +1503,7 @@ impl ProxyServer {
                                         .try_send(AddRouteMessage {
                                             stream_key,
                                             route: route_query_response.clone(),
+                                            is_secondary: false,
                                         })
                                         .expect("ProxyServer is dead");
                                     ProxyServer::try_transmit_to_hopper(
@@ -524,12 +1526,19 @@ impl ProxyServer {
                                     .expect("Could not transmit to hopper");
                                 }
                                 Ok(None) => {
+                                    let hostname = ProxyServer::hostname(&payload);
                                     ProxyServer::handle_route_failure(
                                         payload,
                                         &logger,
                                         source_addr,
                                         &dispatcher,
                                     );
+                                    to_ui_message_sub
+                                        .try_send(NodeToUiMessage {
+                                            target: AllClients,
+                                            body: UiRouteFailureBroadcast { hostname }.tmb(0),
+                                        })
+                                        .expect("UiGateway is dead");
                                 }
                                 Err(e) => {
                                     error!(
@@ -545,6 +1554,288 @@ impl ProxyServer {
         }
     }
 
+    // Striping sends odd-numbered packets over the second route; duplicating sends every packet
+    // over both. A stream whose second route hasn't arrived yet (or never will) just uses the first.
+    fn outbound_routes(
+        &self,
+        stream_key: &StreamKey,
+        sequence_number: u64,
+        primary: &RouteQueryResponse,
+    ) -> Vec<RouteQueryResponse> {
+        match (self.multipath_mode, self.secondary_routes.get(stream_key)) {
+            (MultipathMode::Stripe, Some(secondary)) if sequence_number % 2 == 1 => {
+                vec![secondary.clone()]
+            }
+            (MultipathMode::Duplicate, Some(secondary)) => vec![primary.clone(), secondary.clone()],
+            _ => vec![primary.clone()],
+        }
+    }
+
+    fn request_secondary_route(&self, stream_key: StreamKey, primary: &RouteQueryResponse) {
+        let (over, back) = match &primary.expected_services {
+            ExpectedServices::RoundTrip(over, back, _) => (over, back),
+            ExpectedServices::OneWay(_) => return,
+        };
+        let exit_key = match over.iter().find_map(|service| match service {
+            ExpectedService::Exit(key, _, _) => Some(key.clone()),
+            _ => None,
+        }) {
+            Some(exit_key) => exit_key,
+            None => return,
+        };
+        let mut relay_keys: Vec<PublicKey> = vec![];
+        over.iter()
+            .chain(back.iter())
+            .for_each(|service| match service {
+                ExpectedService::Routing(key, _, _) if !relay_keys.contains(key) => {
+                    relay_keys.push(key.clone())
+                }
+                _ => (),
+            });
+        if relay_keys.is_empty() {
+            return;
+        }
+        let route_query_message = RouteQueryMessage::data_indefinite_route_request(over.len() - 1)
+            .with_target_key(exit_key.clone())
+            .avoiding(relay_keys);
+        let add_route_sub = self.out_subs("ProxyServer").add_route.clone();
+        let logger = self.logger.clone();
+        tokio::spawn(
+            self.out_subs("Neighborhood")
+                .route_source
+                .send(route_query_message)
+                .then(move |route_result| {
+                    match route_result {
+                        Ok(Some(route_query_response)) => add_route_sub
+                            .try_send(AddRouteMessage {
+                                stream_key,
+                                route: route_query_response,
+                                is_secondary: true,
+                            })
+                            .expect("ProxyServer is dead"),
+                        Ok(None) => debug!(
+                            logger,
+                            "No second route to exit Node {} for stream {}; staying on one route",
+                            exit_key,
+                            stream_key
+                        ),
+                        Err(e) => error!(
+                            logger,
+                            "Neighborhood refused to answer route request: {}", e
+                        ),
+                    };
+                    Ok(())
+                }),
+        );
+    }
+
+    fn add_secondary_route(&mut self, stream_key: StreamKey, route: RouteQueryResponse) {
+        if !self.stream_key_routes.contains_key(&stream_key) {
+            debug!(
+                self.logger,
+                "Stream {} ended before its second route arrived", stream_key
+            );
+            return;
+        }
+        debug!(
+            self.logger,
+            "Stream {} now uses a second route: {:?}", stream_key, self.multipath_mode
+        );
+        self.secondary_routes.insert(stream_key, route);
+    }
+
+    // An application rule overrides the split-tunnel rules
+    fn minimum_hop_count(
+        &self,
+        target_hostname_opt: Option<&str>,
+        app_action_opt: Option<AppAction>,
+    ) -> usize {
+        let routes_through_masq = match app_action_opt {
+            Some(action) => action == AppAction::Masq,
+            None => self
+                .split_tunnel_rules
+                .routes_through_masq(target_hostname_opt),
+        };
+        if self.is_decentralized && routes_through_masq {
+            DEFAULT_MINIMUM_HOP_COUNT
+        } else {
+            0
+        }
+    }
+
+    fn handle_client_datagram(&mut self, msg: InboundClientDatagram) {
+        let stream_key = match self.find_udp_association(msg.peer_addr) {
+            Some(stream_key) => stream_key,
+            None => {
+                warning!(
+                    self.logger,
+                    "Discarding {}-byte datagram from {}: no SOCKS client has a UDP association for it",
+                    msg.data.len(),
+                    msg.peer_addr
+                );
+                return;
+            }
+        };
+        let (host, data) = match parse_udp_datagram(&msg.data) {
+            Ok(host_and_data) => host_and_data,
+            Err(e) => {
+                warning!(
+                    self.logger,
+                    "Discarding datagram from {}: {}",
+                    msg.peer_addr,
+                    e
+                );
+                return;
+            }
+        };
+        if self.consuming_wallet_balance.is_none() && self.is_decentralized {
+            error!(
+                self.logger,
+                "UDP datagram rejected due to missing consuming wallet"
+            );
+            return;
+        }
+        let payload = ClientUdpRequestPayload_0v1 {
+            stream_key,
+            target_hostname: host.name,
+            target_port: host.port.expect("SOCKS datagram without a port"),
+            data: PlainData::new(data),
+            originator_public_key: self.alias_cryptde.public_key().clone(),
+        };
+        let minimum_hop_count = self.minimum_hop_count(Some(&payload.target_hostname), None);
+        let route_source = self.out_subs("Neighborhood").route_source.clone();
+        let hopper = self.out_subs("Hopper").hopper.clone();
+        let accountant_exit_sub = self.out_subs("Accountant").accountant_exit.clone();
+        let accountant_routing_sub = self.out_subs("Accountant").accountant_routing.clone();
+        let add_return_route_sub = self.out_subs("ProxyServer").add_return_route.clone();
+        let add_route_sub = self.out_subs("ProxyServer").add_route.clone();
+        let cryptde = self.main_cryptde.dup();
+        let logger = self.logger.clone();
+        match self.stream_key_routes.get(&stream_key) {
+            Some(route_query_response) => ProxyServer::transmit_udp_to_hopper(
+                cryptde,
+                &hopper,
+                route_query_response.clone(),
+                payload,
+                &logger,
+                &accountant_exit_sub,
+                &accountant_routing_sub,
+                &add_return_route_sub,
+            ),
+            None => {
+                let route_query_message =
+                    RouteQueryMessage::data_indefinite_route_request(minimum_hop_count)
+                        .with_exit_target(
+                            Some(payload.target_hostname.clone()),
+                            payload.target_port,
+                        );
+                tokio::spawn(
+                    route_source
+                        .send(route_query_message)
+                        .then(move |route_result| {
+                            match route_result {
+                                Ok(Some(route_query_response)) => {
+                                    add_route_sub
+                                        .try_send(AddRouteMessage {
+                                            stream_key,
+                                            route: route_query_response.clone(),
+                                            is_secondary: false,
+                                        })
+                                        .expect("ProxyServer is dead");
+                                    ProxyServer::transmit_udp_to_hopper(
+                                        cryptde,
+                                        &hopper,
+                                        route_query_response,
+                                        payload,
+                                        &logger,
+                                        &accountant_exit_sub,
+                                        &accountant_routing_sub,
+                                        &add_return_route_sub,
+                                    );
+                                }
+                                Ok(None) => error!(
+                                    logger,
+                                    "Failed to find route to {}", payload.target_hostname
+                                ),
+                                Err(e) => error!(
+                                    logger,
+                                    "Neighborhood refused to answer route request: {}", e
+                                ),
+                            };
+                            Ok(())
+                        }),
+                );
+            }
+        }
+    }
+
+    // A client that named its port is matched on that; otherwise, the first datagram from a new
+    // address is taken to belong to whichever association is still waiting for one.
+    fn find_udp_association(&mut self, peer_addr: SocketAddr) -> Option<StreamKey> {
+        if let Some((stream_key, _)) = self
+            .udp_associations
+            .iter()
+            .find(|(_, association)| association.client_addr_opt == Some(peer_addr))
+        {
+            return Some(*stream_key);
+        }
+        let (stream_key, association) =
+            self.udp_associations.iter_mut().find(|(_, association)| {
+                association.client_addr_opt.is_none()
+                    && (association.client_port == 0 || association.client_port == peer_addr.port())
+            })?;
+        association.client_addr_opt = Some(peer_addr);
+        Some(*stream_key)
+    }
+
+    fn handle_client_udp_response_payload(
+        &mut self,
+        msg: &ExpiredCoresPackage<ClientUdpResponsePayload_0v1>,
+    ) {
+        let return_route_info = match self.get_return_route_info(&msg.remaining_route) {
+            Some(rri) => rri,
+            None => return,
+        };
+        let response = &msg.payload;
+        let client_addr = match self
+            .udp_associations
+            .get(&response.stream_key)
+            .and_then(|association| association.client_addr_opt)
+        {
+            Some(client_addr) => client_addr,
+            None => {
+                error!(
+                    self.logger,
+                    "Discarding {}-byte datagram from {} for an unrecognized UDP association: {:?}",
+                    response.data.len(),
+                    response.source,
+                    response.stream_key
+                );
+                return;
+            }
+        };
+        self.report_response_services_consumed(
+            &return_route_info,
+            response.data.len(),
+            msg.payload_len,
+        );
+        let datagram = make_udp_datagram(response.source, response.data.as_slice());
+        if let Err(e) = self
+            .udp_relay_opt
+            .as_ref()
+            .expect("UDP association without a UDP relay")
+            .send_to(&datagram, client_addr)
+        {
+            warning!(
+                self.logger,
+                "Could not relay {}-byte datagram to SOCKS client {}: {}",
+                datagram.len(),
+                client_addr,
+                e
+            );
+        }
+    }
+
     fn handle_stream_shutdown_msg(&mut self, msg: StreamShutdownMsg) {
         let nca = match msg.stream_type {
             RemovedStreamType::Clandestine => {
@@ -564,10 +1855,15 @@ impl ProxyServer {
             }
             Some(sk) => sk,
         };
-        if msg.report_to_counterpart {
+        let has_counterpart = (!self.socks_sessions.contains_key(&stream_key)
+            || self.tunneled_hosts.contains_key(&stream_key))
+            && !self.encrypted_sni_streams.contains_key(&stream_key);
+        if msg.report_to_counterpart && has_counterpart {
             debug!(
                 self.logger,
-                "Reporting shutdown of {} to counterpart", &stream_key
+                "Reporting {}shutdown of {} to counterpart",
+                if nca.half_closed { "half-" } else { "" },
+                &stream_key
             );
             let ibcd = InboundClientData {
                 peer_addr: msg.peer_addr,
@@ -577,7 +1873,8 @@ impl ProxyServer {
                 sequence_number: Some(nca.sequence_number),
                 data: vec![],
             };
-            self.handle_normal_client_data(ibcd, true);
+            // After a half-close, the stream key stays until the server's last data comes back
+            self.handle_normal_client_data(ibcd, !nca.half_closed);
         } else {
             debug!(
                 self.logger,
@@ -587,6 +1884,85 @@ impl ProxyServer {
         }
     }
 
+    // Requests that change nothing on the server: HTTP GETs and the like, and TLS client hellos
+    fn is_replayable(&self, msg: &InboundClientData, stream_key: &StreamKey) -> bool {
+        if msg.last_data
+            || msg.sequence_number != Some(0)
+            || self.tunneled_hosts.contains_key(stream_key)
+            || self.socks_sessions.contains_key(stream_key)
+        {
+            return false;
+        }
+        match msg.reception_port {
+            Some(HTTP_PORT) => ["GET ", "HEAD ", "OPTIONS "]
+                .iter()
+                .any(|method| msg.data.starts_with(method.as_bytes())),
+            Some(TLS_PORT) => msg.data.len() > 5 && msg.data[0] == 0x16 && msg.data[5] == 0x01,
+            _ => false,
+        }
+    }
+
+    // A stalled stream starts over under a new stream key, so that whatever its first route
+    // delivers late is discarded rather than mixed in with the replay's responses. There's no
+    // telling which hop went missing, so the new route at least avoids the old exit Node.
+    fn replay_stalled_requests(&mut self, now: Instant) {
+        let stream_replay_timeout = self.stream_replay_timeout;
+        let (stalled, waiting): (HashMap<_, _>, HashMap<_, _>) =
+            self.replayable_requests.drain().partition(|(_, request)| {
+                now.saturating_duration_since(request.sent_at) >= stream_replay_timeout
+            });
+        self.replayable_requests = waiting;
+        stalled.into_iter().for_each(|(stream_key, request)| {
+            // Zero-hop streams have no hops to lose, and routeless ones have nothing to replay on
+            let exit_key_opt = match self.stream_key_routes.get(&stream_key) {
+                Some(route) if ProxyServer::hop_count(&route.expected_services) > 0 => {
+                    match &route.expected_services {
+                        ExpectedServices::RoundTrip(over, _, _) => {
+                            over.iter().find_map(|service| match service {
+                                ExpectedService::Exit(key, _, _) => Some(key.clone()),
+                                _ => None,
+                            })
+                        }
+                        ExpectedServices::OneWay(_) => None,
+                    }
+                }
+                _ => return,
+            };
+            if request.replays >= MAX_STREAM_REPLAYS {
+                warning!(
+                    self.logger,
+                    "Giving up on stream {}: no response after {} replays",
+                    stream_key,
+                    request.replays
+                );
+                return;
+            }
+            if let Some(exit_key) = exit_key_opt {
+                self.out_subs("Neighborhood")
+                    .update_node_record_metadata
+                    .try_send(NodeRecordMetadataMessage::Desirable(exit_key, false))
+                    .expect("Neighborhood is dead");
+            }
+            let replays = request.replays + 1;
+            let replay_key = stream_key.retried(replays);
+            info!(
+                self.logger,
+                "Replaying request from {} on a new route as stream {}: stream {} got no response in {:?}",
+                request.msg.peer_addr,
+                replay_key,
+                stream_key,
+                stream_replay_timeout
+            );
+            self.purge_stream_key(&stream_key);
+            self.keys_and_addrs.insert(replay_key, request.msg.peer_addr);
+            metrics::ORIGINATING_STREAMS.set(self.keys_and_addrs.len() as u64);
+            self.handle_normal_client_data(request.msg, false);
+            if let Some(replayed) = self.replayable_requests.get_mut(&replay_key) {
+                replayed.replays = replays;
+            }
+        });
+    }
+
     fn make_stream_key(&mut self, ibcd: &InboundClientData) -> StreamKey {
         match self.keys_and_addrs.b_to_a(&ibcd.peer_addr) {
             Some(stream_key) => {
@@ -603,6 +1979,7 @@ impl ProxyServer {
                     .stream_key_factory
                     .make(&self.main_cryptde.public_key(), ibcd.peer_addr);
                 self.keys_and_addrs.insert(stream_key, ibcd.peer_addr);
+                metrics::ORIGINATING_STREAMS.set(self.keys_and_addrs.len() as u64);
                 debug!(
                     self.logger,
                     "make_stream_key() inserted new key {} for {}", &stream_key, ibcd.peer_addr
@@ -614,21 +1991,193 @@ impl ProxyServer {
 
     fn purge_stream_key(&mut self, stream_key: &StreamKey) {
         let _ = self.keys_and_addrs.remove_a(stream_key);
+        metrics::ORIGINATING_STREAMS.set(self.keys_and_addrs.len() as u64);
         let _ = self.stream_key_routes.remove(stream_key);
+        let _ = self.secondary_routes.remove(stream_key);
+        let _ = self.receive_windows.remove(stream_key);
         let _ = self.tunneled_hosts.remove(stream_key);
+        let _ = self.socks_sessions.remove(stream_key);
+        let _ = self.udp_associations.remove(stream_key);
+        let _ = self.encrypted_sni_streams.remove(stream_key);
+        let _ = self.stream_stats.remove(stream_key);
+        let _ = self.replayable_requests.remove(stream_key);
+        let _ = self.resumable_streams.remove(stream_key);
     }
 
-    fn make_payload(
+    // Only the first request of a new plain HTTP connection can take over an old stream
+    fn may_resume(&self, msg: &InboundClientData, stream_key: &StreamKey) -> bool {
+        !self.resumption_tokens.is_empty()
+            && msg.sequence_number == Some(0)
+            && msg.reception_port == Some(HTTP_PORT)
+            && !msg.last_data
+            && !self.stream_key_routes.contains_key(stream_key)
+            && !self.tunneled_hosts.contains_key(stream_key)
+            && !self.socks_sessions.contains_key(stream_key)
+    }
+
+    // A new connection to a host that a stream from before the restart sat idle on takes that
+    // stream over, and with it the exit Node's connection to the server
+    fn resume_stream(
         &mut self,
-        ibcd: InboundClientData,
-        stream_key: &StreamKey,
-    ) -> Result<ClientRequestPayload_0v1, ()> {
-        let tunnelled_host = self.tunneled_hosts.get(stream_key);
-        let new_ibcd = match tunnelled_host {
-            Some(_) => InboundClientData {
-                reception_port: Some(443),
-                ..ibcd
-            },
+        stream_key: StreamKey,
+        payload: &mut ClientRequestPayload_0v1,
+        msg: InboundClientData,
+    ) -> Option<ResumptionToken> {
+        let now = now_time_t();
+        self.resumption_tokens
+            .retain(|token| token.expires_at > now);
+        let hostname = payload.target_hostname.clone()?;
+        let index = self
+            .resumption_tokens
+            .iter()
+            .position(|token| token.hostname == hostname && token.port == payload.target_port)?;
+        let token = self.resumption_tokens.remove(index);
+        info!(
+            self.logger,
+            "Resuming stream {} to {}:{} from before the restart for {}",
+            token.stream_key,
+            hostname,
+            token.port,
+            msg.peer_addr
+        );
+        let _ = self.keys_and_addrs.remove_a(&stream_key);
+        self.keys_and_addrs.insert(token.stream_key, msg.peer_addr);
+        self.receive_windows.insert(
+            token.stream_key,
+            ReceiveWindow::starting_at(token.next_response_sequence),
+        );
+        let mut stream = ResumableStream::new(hostname, token.port, Instant::now());
+        stream.request_offset = token.next_request_sequence;
+        stream.response_offset = token.next_response_sequence;
+        stream.resumed_request_opt = Some(msg);
+        self.resumable_streams.insert(token.stream_key, stream);
+        payload.stream_key = token.stream_key;
+        payload.is_resumption = true;
+        Some(token)
+    }
+
+    // Keeps count of a plain HTTP stream's requests, and shifts them past the sequence numbers
+    // an earlier run of this Node used, if the stream was resumed
+    fn track_resumable_stream(&mut self, payload: &mut ClientRequestPayload_0v1) {
+        if self.resumption_store_opt.is_none()
+            || payload.protocol != ProxyProtocol::HTTP
+            || self.tunneled_hosts.contains_key(&payload.stream_key)
+            || self.socks_sessions.contains_key(&payload.stream_key)
+        {
+            return;
+        }
+        let hostname = match payload.target_hostname.as_ref() {
+            Some(hostname) => hostname.clone(),
+            None => return,
+        };
+        let port = payload.target_port;
+        let stream = self
+            .resumable_streams
+            .entry(payload.stream_key)
+            .or_insert_with(|| ResumableStream::new(hostname, port, Instant::now()));
+        payload.sequenced_packet.sequence_number += stream.request_offset;
+        stream.next_request_sequence = payload.sequenced_packet.sequence_number + 1;
+        stream.awaiting_response = true;
+        stream.last_activity = Instant::now();
+        if payload.sequenced_packet.last_data {
+            let _ = self.resumable_streams.remove(&payload.stream_key);
+        }
+    }
+
+    // Idle plain HTTP streams are written down, so that after a restart new connections to the
+    // same hosts can take them over before the exit Nodes let their server connections go
+    fn checkpoint_resumable_streams(&mut self, now: Instant) {
+        let expires_at = now_time_t() + RESUMPTION_TOKEN_TTL.as_secs() as i64;
+        let tokens = self
+            .resumable_streams
+            .iter()
+            .filter(|(_, stream)| {
+                !stream.awaiting_response && now >= stream.last_activity + RESUMPTION_QUIET_PERIOD
+            })
+            .filter_map(|(stream_key, stream)| {
+                let window = self
+                    .receive_windows
+                    .get(stream_key)
+                    .filter(|window| window.is_settled())?;
+                let exit_key = self
+                    .stream_key_routes
+                    .get(stream_key)
+                    .and_then(|route| ProxyServer::exit_key_opt(&route.expected_services))?;
+                Some(ResumptionToken {
+                    stream_key: *stream_key,
+                    exit_key,
+                    hostname: stream.hostname.clone(),
+                    port: stream.port,
+                    next_request_sequence: stream.next_request_sequence,
+                    next_response_sequence: window.next_expected(),
+                    expires_at,
+                })
+            })
+            .collect::<Vec<ResumptionToken>>();
+        if tokens.is_empty() && !self.resumption_checkpointed {
+            return;
+        }
+        self.resumption_checkpointed = !tokens.is_empty();
+        if let Some(store) = self.resumption_store_opt.as_ref() {
+            store.save(&tokens);
+        }
+    }
+
+    fn exit_key_opt(expected_services: &ExpectedServices) -> Option<PublicKey> {
+        match expected_services {
+            ExpectedServices::RoundTrip(over, _, _) => {
+                over.iter().find_map(|service| match service {
+                    ExpectedService::Exit(key, _, _) => Some(key.clone()),
+                    _ => None,
+                })
+            }
+            ExpectedServices::OneWay(_) => None,
+        }
+    }
+
+    // The exit Node no longer had the stream: the request starts over on a stream of its own
+    fn restart_refused_resumption(&mut self, stream_key: &StreamKey) {
+        let msg = match self
+            .resumable_streams
+            .get_mut(stream_key)
+            .and_then(|stream| stream.resumed_request_opt.take())
+        {
+            Some(msg) => msg,
+            None => return,
+        };
+        let fresh_key = stream_key.retried(1);
+        info!(
+            self.logger,
+            "Exit Node no longer had stream {}; starting the request from {} over as stream {}",
+            stream_key,
+            msg.peer_addr,
+            fresh_key
+        );
+        self.purge_stream_key(stream_key);
+        self.keys_and_addrs.insert(fresh_key, msg.peer_addr);
+        metrics::ORIGINATING_STREAMS.set(self.keys_and_addrs.len() as u64);
+        self.handle_normal_client_data(msg, false);
+    }
+
+    fn make_payload(
+        &mut self,
+        ibcd: InboundClientData,
+        stream_key: &StreamKey,
+    ) -> Result<ClientRequestPayload_0v1, ()> {
+        let tunnelled_host = self.tunneled_hosts.get(stream_key);
+        let sequence_offset = self
+            .socks_sessions
+            .get(stream_key)
+            .map(|session| session.packets_received)
+            .unwrap_or(0);
+        let new_ibcd = match tunnelled_host {
+            Some(host) => InboundClientData {
+                reception_port: Some(Self::tunnel_reception_port(host)),
+                sequence_number: ibcd
+                    .sequence_number
+                    .map(|sequence_number| sequence_number - sequence_offset),
+                ..ibcd
+            },
             None => ibcd,
         };
         match self.client_request_payload_factory.make(
@@ -642,8 +2191,9 @@ impl ProxyServer {
                 Err(())
             }
             Some(payload) => match tunnelled_host {
-                Some(hostname) => Ok(ClientRequestPayload_0v1 {
-                    target_hostname: Some(hostname.clone()),
+                Some(host) => Ok(ClientRequestPayload_0v1 {
+                    target_hostname: Some(host.name.clone()),
+                    target_port: host.port.unwrap_or(payload.target_port),
                     ..payload
                 }),
                 None => Ok(payload),
@@ -651,6 +2201,47 @@ impl ProxyServer {
         }
     }
 
+    // Only the first packet of a stream says what protocol it speaks. Tunnels and SOCKS sessions
+    // have named their hosts already.
+    fn sniff_if_unrecognized(&self, msg: &InboundClientData, stream_key: &StreamKey) {
+        if msg.sequence_number != Some(0)
+            || msg.data.is_empty()
+            || self.tunneled_hosts.contains_key(stream_key)
+            || self.socks_sessions.contains_key(stream_key)
+        {
+            return;
+        }
+        let recognized = msg
+            .reception_port
+            .and_then(from_standard_port)
+            .map(|pack| pack.find_host(&PlainData::new(&msg.data)).is_some())
+            .unwrap_or(false);
+        if !recognized {
+            debug!(
+                self.logger,
+                "Keeping the first {} bytes from {} for diagnostics: no protocol recognized",
+                msg.data.len(),
+                msg.peer_addr
+            );
+            SNIFFED_STREAMS.capture(
+                msg.peer_addr,
+                msg.reception_port,
+                &msg.data,
+                SystemTime::now(),
+            );
+        }
+    }
+
+    // Data inside a tunnel is treated as TLS unless it's obviously headed for a web server or a
+    // mail submission server
+    fn tunnel_reception_port(host: &Host) -> u16 {
+        match host.port {
+            Some(port) if port == HTTP_PORT => HTTP_PORT,
+            Some(port) if port == SMTP_SUBMISSION_PORT => SMTP_SUBMISSION_PORT,
+            _ => TLS_PORT,
+        }
+    }
+
     #[allow(clippy::too_many_arguments)]
     fn try_transmit_to_hopper(
         cryptde: Box<dyn CryptDE>,
@@ -683,7 +2274,7 @@ impl ProxyServer {
                 ProxyServer::report_exit_service(
                     accountant_exit_sub,
                     over.clone(),
-                    &payload,
+                    payload.sequenced_packet.data.len(),
                     &logger,
                 );
                 ProxyServer::transmit_to_hopper(
@@ -740,7 +2331,7 @@ impl ProxyServer {
     fn report_exit_service(
         accountant_exit_sub: &Recipient<ReportExitServiceConsumedMessage>,
         expected_services: Vec<ExpectedService>,
-        payload: &ClientRequestPayload_0v1,
+        payload_size: usize,
         logger: &Logger,
     ) {
         match expected_services
@@ -752,7 +2343,6 @@ impl ProxyServer {
                 _ => None,
             }) {
             Some((earning_wallet, _rate_pack)) => {
-                let payload_size = payload.sequenced_packet.data.len();
                 let report_exit_service_consumed_message = ReportExitServiceConsumedMessage {
                     earning_wallet: earning_wallet.clone(),
                     payload_size,
@@ -780,18 +2370,10 @@ impl ProxyServer {
         accountant_routing_sub: &Recipient<ReportRoutingServiceConsumedMessage>,
         retire_stream_key_via: Option<&Recipient<StreamShutdownMsg>>,
     ) {
-        let destination_key_opt = if !expected_services.is_empty()
-            && expected_services
-                .iter()
-                .all(|expected_service| matches!(expected_service, ExpectedService::Nothing))
-        {
-            Some(payload.originator_public_key.clone())
-        } else {
-            expected_services.iter().find_map(|service| match service {
-                ExpectedService::Exit(public_key, _, _) => Some(public_key.clone()),
-                _ => None,
-            })
-        };
+        let destination_key_opt = ProxyServer::payload_destination_key(
+            &expected_services,
+            &payload.originator_public_key,
+        );
 
         match destination_key_opt {
             None => ProxyServer::handle_route_failure(payload, &logger, source_addr, dispatcher),
@@ -828,6 +2410,7 @@ impl ProxyServer {
                                     // No report to counterpart; these are irrelevant
                                     reception_port: 0,
                                     sequence_number: 0,
+                                    half_closed: false,
                                 },
                             ),
                             report_to_counterpart: false,
@@ -838,6 +2421,84 @@ impl ProxyServer {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
+    fn transmit_udp_to_hopper(
+        cryptde: Box<dyn CryptDE>,
+        hopper: &Recipient<IncipientCoresPackage>,
+        route_query_response: RouteQueryResponse,
+        payload: ClientUdpRequestPayload_0v1,
+        logger: &Logger,
+        accountant_exit_sub: &Recipient<ReportExitServiceConsumedMessage>,
+        accountant_routing_sub: &Recipient<ReportRoutingServiceConsumedMessage>,
+        add_return_route_sub: &Recipient<AddReturnRouteMessage>,
+    ) {
+        let (over, back, return_route_id) = match route_query_response.expected_services {
+            ExpectedServices::RoundTrip(over, back, return_route_id) => {
+                (over, back, return_route_id)
+            }
+            _ => panic!("Expected RoundTrip ExpectedServices but got OneWay"),
+        };
+        let payload_destination_key =
+            match ProxyServer::payload_destination_key(&over, &payload.originator_public_key) {
+                Some(key) => key,
+                None => {
+                    error!(
+                        logger,
+                        "Failed to find route to {}", payload.target_hostname
+                    );
+                    return;
+                }
+            };
+        add_return_route_sub
+            .try_send(AddReturnRouteMessage {
+                return_route_id,
+                expected_services: back,
+                // Only consulted for DNS failures, which the exit Node never reports for UDP
+                protocol: ProxyProtocol::TLS,
+                server_name: Some(payload.target_hostname.clone()),
+            })
+            .expect("ProxyServer is dead");
+        ProxyServer::report_exit_service(
+            accountant_exit_sub,
+            over.clone(),
+            payload.data.len(),
+            logger,
+        );
+        let pkg = IncipientCoresPackage::new(
+            cryptde.as_ref(),
+            route_query_response.route,
+            payload.into(),
+            &payload_destination_key,
+        )
+        .expect("Key magically disappeared");
+        ProxyServer::report_routing_service(
+            accountant_routing_sub,
+            over,
+            pkg.payload.len(),
+            logger,
+        );
+        hopper.try_send(pkg).expect("Hopper is dead");
+    }
+
+    // Zero-hop payloads go to the originator itself; everything else goes to the exit Node
+    fn payload_destination_key(
+        expected_services: &[ExpectedService],
+        originator_public_key: &PublicKey,
+    ) -> Option<PublicKey> {
+        if !expected_services.is_empty()
+            && expected_services
+                .iter()
+                .all(|expected_service| matches!(expected_service, ExpectedService::Nothing))
+        {
+            Some(originator_public_key.clone())
+        } else {
+            expected_services.iter().find_map(|service| match service {
+                ExpectedService::Exit(public_key, _, _) => Some(public_key.clone()),
+                _ => None,
+            })
+        }
+    }
+
     fn handle_route_failure(
         payload: ClientRequestPayload_0v1,
         logger: &Logger,
@@ -862,6 +2523,7 @@ impl ProxyServer {
             last_data: true,
             sequence_number: Some(0),
             data,
+            relayed_from_opt: None,
         };
         dispatcher.try_send(msg).expect("Dispatcher is dead");
     }
@@ -961,7 +2623,7 @@ mod tests {
     use crate::sub_lib::dispatcher::Component;
     use crate::sub_lib::hop::LiveHop;
     use crate::sub_lib::hopper::MessageType;
-    use crate::sub_lib::neighborhood::ExpectedServices;
+    use crate::sub_lib::neighborhood::{ExitTarget, ExpectedServices};
     use crate::sub_lib::neighborhood::{ExpectedService, DEFAULT_RATE_PACK};
     use crate::sub_lib::proxy_client::{ClientResponsePayload_0v1, DnsResolveFailure_0v1};
     use crate::sub_lib::proxy_server::ClientRequestPayload_0v1;
@@ -969,12 +2631,14 @@ mod tests {
     use crate::sub_lib::route::Route;
     use crate::sub_lib::route::RouteSegment;
     use crate::sub_lib::sequence_buffer::SequencedPacket;
+    use crate::sub_lib::sequence_window::ACK_INTERVAL;
     use crate::sub_lib::ttl_hashmap::TtlHashMap;
     use crate::sub_lib::versioned_data::VersionedData;
     use crate::sub_lib::wallet::Wallet;
     use crate::test_utils::logging::init_test_logging;
     use crate::test_utils::logging::TestLogHandler;
     use crate::test_utils::make_meaningless_stream_key;
+    use crate::test_utils::persistent_configuration_mock::PersistentConfigurationMock;
     use crate::test_utils::recorder::make_recorder;
     use crate::test_utils::recorder::peer_actors_builder;
     use crate::test_utils::recorder::Recorder;
@@ -984,9 +2648,10 @@ mod tests {
     use crate::test_utils::{main_cryptde, make_wallet};
     use crate::test_utils::{make_meaningless_route, make_paying_wallet};
     use actix::System;
-    use masq_lib::constants::{HTTP_PORT, TLS_PORT};
+    use masq_lib::constants::{HTTP_PORT, SMTP_SUBMISSION_PORT, TLS_PORT};
     use masq_lib::test_utils::utils::DEFAULT_CHAIN_ID;
     use std::cell::RefCell;
+    use std::io;
     use std::net::SocketAddr;
     use std::str::FromStr;
     use std::sync::mpsc;
@@ -1013,6 +2678,8 @@ mod tests {
                 add_return_route: addr.clone().recipient::<AddReturnRouteMessage>(),
                 add_route: addr.clone().recipient::<AddRouteMessage>(),
                 stream_shutdown_sub: addr.clone().recipient::<StreamShutdownMsg>(),
+                to_ui_message_sub: addr.clone().recipient::<NodeToUiMessage>(),
+                streams_query: addr.recipient::<StreamsQuery>(),
             }
         }
     }
@@ -1054,6 +2721,58 @@ mod tests {
         }
     }
 
+    type SendToParameters = Arc<Mutex<Vec<(Vec<u8>, SocketAddr)>>>;
+
+    struct UdpRelayMock {
+        send_to_params: SendToParameters,
+    }
+
+    impl UdpRelay for UdpRelayMock {
+        fn send_to(&self, datagram: &[u8], peer_addr: SocketAddr) -> io::Result<usize> {
+            self.send_to_params
+                .lock()
+                .unwrap()
+                .push((datagram.to_vec(), peer_addr));
+            Ok(datagram.len())
+        }
+    }
+
+    struct UdpRelayFactoryMock {
+        make_parameters: Arc<Mutex<Vec<u16>>>,
+        make_results: RefCell<Vec<io::Result<Box<dyn UdpRelay>>>>,
+    }
+
+    impl UdpRelayFactory for UdpRelayFactoryMock {
+        fn make(
+            &self,
+            port: u16,
+            _datagram_sub: Recipient<InboundClientDatagram>,
+            _logger: Logger,
+        ) -> io::Result<Box<dyn UdpRelay>> {
+            self.make_parameters.lock().unwrap().push(port);
+            self.make_results.borrow_mut().remove(0)
+        }
+    }
+
+    impl UdpRelayFactoryMock {
+        fn new() -> UdpRelayFactoryMock {
+            UdpRelayFactoryMock {
+                make_parameters: Arc::new(Mutex::new(vec![])),
+                make_results: RefCell::new(vec![]),
+            }
+        }
+
+        fn make_parameters(mut self, params: &Arc<Mutex<Vec<u16>>>) -> UdpRelayFactoryMock {
+            self.make_parameters = params.clone();
+            self
+        }
+
+        fn make_result(self, result: io::Result<Box<dyn UdpRelay>>) -> UdpRelayFactoryMock {
+            self.make_results.borrow_mut().push(result);
+            self
+        }
+    }
+
     fn return_route_with_id(cryptde: &dyn CryptDE, return_route_id: u32) -> Route {
         let cover_hop = make_cover_hop(cryptde);
         let id_hop = cryptde
@@ -1075,6 +2794,7 @@ mod tests {
                 public_key: cryptde.public_key().clone(),
                 payer: None,
                 component: Component::ProxyServer,
+                timing_key_opt: None,
             },
         )
         .unwrap()
@@ -1150,6 +2870,7 @@ mod tests {
             target_port: HTTP_PORT,
             protocol: ProxyProtocol::HTTP,
             originator_public_key: alias_cryptde.public_key().clone(),
+            is_resumption: false,
         };
         let expected_pkg = IncipientCoresPackage::new(
             main_cryptde,
@@ -1170,6 +2891,7 @@ mod tests {
                 alias_cryptde,
                 false,
                 Some(STANDARD_CONSUMING_WALLET_BALANCE),
+                None,
             );
             subject.stream_key_factory = Box::new(stream_key_factory);
             let subject_addr: Addr<ProxyServer> = subject.start();
@@ -1197,7 +2919,11 @@ mod tests {
         );
         let recording = neighborhood_recording_arc.lock().unwrap();
         let record = recording.get_record::<RouteQueryMessage>(0);
-        assert_eq!(record, &RouteQueryMessage::data_indefinite_route_request(0));
+        assert_eq!(
+            record,
+            &RouteQueryMessage::data_indefinite_route_request(0)
+                .with_exit_target(Some("nowhere.com".to_string()), 80)
+        );
         let recording = proxy_server_recording_arc.lock().unwrap();
         assert_eq!(recording.len(), 0);
     }
@@ -1242,6 +2968,7 @@ mod tests {
             last_data: false,
             sequence_number: Some(0),
             data: b"HTTP/1.1 200 OK\r\n\r\n".to_vec(),
+            relayed_from_opt: None,
         };
 
         let expected_payload = ClientRequestPayload_0v1 {
@@ -1255,6 +2982,7 @@ mod tests {
             target_port: 443,
             protocol: ProxyProtocol::TLS,
             originator_public_key: key.clone(),
+            is_resumption: false,
         };
         let expected_pkg =
             IncipientCoresPackage::new(main_cryptde, route.clone(), expected_payload.into(), &key)
@@ -1275,6 +3003,7 @@ mod tests {
                 alias_cryptde,
                 false,
                 Some(STANDARD_CONSUMING_WALLET_BALANCE),
+                None,
             );
             subject.stream_key_factory = Box::new(stream_key_factory);
             let subject_addr: Addr<ProxyServer> = subject.start();
@@ -1311,124 +3040,68 @@ mod tests {
         assert_eq!(
             neighborhood_record,
             &RouteQueryMessage::data_indefinite_route_request(0)
+                .with_exit_target(Some("realdomain.nu".to_string()), 443)
         );
     }
 
     #[test]
-    fn handle_client_response_payload_increments_sequence_number_when_browser_proxy_sequence_offset_is_true(
-    ) {
-        let system = System::new("handle_client_response_payload_increments_sequence_number_when_browser_proxy_sequence_offset_is_true");
-        let (dispatcher_mock, _, dispatcher_log_arc) = make_recorder();
-        let cryptde = main_cryptde();
-        let mut subject = ProxyServer::new(
-            cryptde,
-            alias_cryptde(),
-            false,
-            Some(STANDARD_CONSUMING_WALLET_BALANCE),
-        );
+    fn proxy_server_negotiates_socks5_and_tunnels_subsequent_data_to_requested_host() {
+        let main_cryptde = main_cryptde();
+        let alias_cryptde = alias_cryptde();
+        let key = alias_cryptde.public_key();
+        let (hopper_mock, hopper_awaiter, hopper_recording_arc) = make_recorder();
+        let (neighborhood_mock, _, _) = make_recorder();
+        let neighborhood_mock = neighborhood_mock.route_query_response(Some(
+            zero_hop_route_response(&main_cryptde.public_key(), main_cryptde),
+        ));
+        let route = zero_hop_route_response(main_cryptde.public_key(), main_cryptde).route;
+        let (dispatcher_mock, _, dispatcher_recording_arc) = make_recorder();
         let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
         let stream_key = make_meaningless_stream_key();
-        subject
-            .keys_and_addrs
-            .insert(stream_key.clone(), socket_addr.clone());
-
-        subject.route_ids_to_return_routes.insert(
-            1234,
-            AddReturnRouteMessage {
-                return_route_id: 1234,
-                expected_services: vec![ExpectedService::Nothing],
-                protocol: ProxyProtocol::TLS,
-                server_name: None,
-            },
-        );
-        let subject_addr: Addr<ProxyServer> = subject.start();
-        let http_request = b"CONNECT https://realdomain.nu:443 HTTP/1.1\r\nHost: https://bunkjunk.wrong:443\r\n\r\n";
-        let request_data = http_request.to_vec();
-        let inbound_client_data = InboundClientData {
+        let make_ibcd = |sequence_number: u64, data: &[u8]| InboundClientData {
             peer_addr: socket_addr,
-            reception_port: Some(443),
+            reception_port: Some(1080),
+            sequence_number: Some(sequence_number),
             last_data: false,
             is_clandestine: false,
-            sequence_number: Some(0),
-            data: request_data,
+            data: data.to_vec(),
         };
-
-        let client_response_payload = ClientResponsePayload_0v1 {
+        let greeting = make_ibcd(0, &[0x05, 0x01, 0x00]);
+        let mut connect_request = vec![0x05, 0x01, 0x00, 0x03, 13];
+        connect_request.extend(b"realdomain.nu");
+        connect_request.extend(&[0x01, 0xBB]);
+        let connect_request = make_ibcd(1, &connect_request);
+        let tunnelled_msg = make_ibcd(2, b"client hello");
+        let expected_payload = ClientRequestPayload_0v1 {
             stream_key,
             sequenced_packet: SequencedPacket {
-                data: b"some data".to_vec(),
+                data: b"client hello".to_vec(),
                 sequence_number: 0,
                 last_data: false,
             },
+            target_hostname: Some(String::from("realdomain.nu")),
+            target_port: 443,
+            protocol: ProxyProtocol::TLS,
+            originator_public_key: key.clone(),
+            is_resumption: false,
         };
-
-        let expired_cores_package: ExpiredCoresPackage<ClientResponsePayload_0v1> =
-            ExpiredCoresPackage::new(
-                SocketAddr::from_str("1.2.3.4:1234").unwrap(),
-                Some(make_wallet("irrelevant")),
-                return_route_with_id(cryptde, 1234),
-                client_response_payload.into(),
-                0,
-            );
-
-        let mut peer_actors = peer_actors_builder().dispatcher(dispatcher_mock).build();
-        peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
-        subject_addr.try_send(BindMessage { peer_actors }).unwrap();
-        subject_addr.try_send(inbound_client_data).unwrap();
-
-        subject_addr
-            .try_send(expired_cores_package.clone())
-            .unwrap();
-
-        System::current().stop();
-        system.run();
-
-        let dispatcher_recording = dispatcher_log_arc.lock().unwrap();
-        let record = dispatcher_recording.get_record::<TransmitDataMsg>(1);
-
-        assert_eq!(record.sequence_number.unwrap(), 1);
-    }
-
-    #[test]
-    fn proxy_server_sends_route_failure_for_connect_requests_to_ports_other_than_443() {
-        let cryptde = main_cryptde();
-        let http_request = b"CONNECT https://realdomain.nu:8443 HTTP/1.1\r\nHost: https://bunkjunk.wrong:443\r\n\r\n";
-
-        let (hopper_mock, _hopper_awaiter, _hopper_recording_arc) = make_recorder();
-        let (neighborhood_mock, _, _neighborhood_recording_arc) = make_recorder();
-        let (dispatcher_mock, _dispatcher_awaiter, dispatcher_recording_arc) = make_recorder();
-
-        let neighborhood_mock = neighborhood_mock.route_query_response(Some(
-            zero_hop_route_response(&cryptde.public_key(), cryptde),
-        ));
-
-        let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
-        let stream_key = make_meaningless_stream_key();
-        let request_data = http_request.to_vec();
-        let msg_from_dispatcher = InboundClientData {
-            peer_addr: socket_addr.clone(),
-            reception_port: Some(8443),
-            sequence_number: Some(0),
-            last_data: false,
-            is_clandestine: false,
-            data: request_data.clone(),
-        };
-
-        let stream_key_parameters_arc = Arc::new(Mutex::new(vec![]));
-        let stream_key_parameters_arc_thread = stream_key_parameters_arc.clone();
+        let expected_pkg =
+            IncipientCoresPackage::new(main_cryptde, route, expected_payload.into(), &key).unwrap();
 
         thread::spawn(move || {
-            let stream_key_factory = StreamKeyFactoryMock::new()
-                .make_parameters(&stream_key_parameters_arc_thread)
-                .make_result(stream_key);
+            let stream_key_factory = StreamKeyFactoryMock::new().make_result(stream_key);
             let system = System::new(
-                "proxy_server_receives_connect_responds_with_ok_and_stores_stream_key_and_hostname",
+                "proxy_server_negotiates_socks5_and_tunnels_subsequent_data_to_requested_host",
             );
             let mut subject = ProxyServer::new(
-                cryptde,
-                alias_cryptde(),
+                main_cryptde,
+                alias_cryptde,
                 false,
                 Some(STANDARD_CONSUMING_WALLET_BALANCE),
+                Some(SocksConfig {
+                    port: 1080,
+                    credentials_opt: None,
+                }),
             );
             subject.stream_key_factory = Box::new(stream_key_factory);
             let subject_addr: Addr<ProxyServer> = subject.start();
@@ -1440,65 +3113,96 @@ mod tests {
             peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
             subject_addr.try_send(BindMessage { peer_actors }).unwrap();
 
-            subject_addr.try_send(msg_from_dispatcher).unwrap();
+            subject_addr.try_send(greeting).unwrap();
+            subject_addr.try_send(connect_request).unwrap();
+            subject_addr.try_send(tunnelled_msg).unwrap();
             system.run();
         });
 
-        thread::sleep(Duration::from_millis(500));
-
-        let expected_transmit_data_msg = TransmitDataMsg {
-            endpoint: Endpoint::Socket(socket_addr),
-            last_data: true,
-            sequence_number: Some(0),
-            data: b"HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\n\r\n".to_vec(),
-        };
-
+        hopper_awaiter.await_message_count(1);
         let dispatcher_recording = dispatcher_recording_arc.lock().unwrap();
-        let record = dispatcher_recording.get_record::<TransmitDataMsg>(0);
-
-        assert_eq!(record, &expected_transmit_data_msg);
+        assert_eq!(
+            dispatcher_recording.get_record::<TransmitDataMsg>(0),
+            &TransmitDataMsg {
+                endpoint: Endpoint::Socket(socket_addr),
+                last_data: false,
+                sequence_number: Some(0),
+                data: vec![0x05, 0x00],
+                relayed_from_opt: None,
+            }
+        );
+        assert_eq!(
+            dispatcher_recording.get_record::<TransmitDataMsg>(1),
+            &TransmitDataMsg {
+                endpoint: Endpoint::Socket(socket_addr),
+                last_data: false,
+                sequence_number: Some(1),
+                data: vec![0x05, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+                relayed_from_opt: None,
+            }
+        );
+        let hopper_recording = hopper_recording_arc.lock().unwrap();
+        let hopper_record = hopper_recording.get_record::<IncipientCoresPackage>(0);
+        assert_eq!(hopper_record, &expected_pkg);
     }
 
     #[test]
-    fn proxy_server_sends_error_and_shuts_down_stream_when_connect_host_unparseable() {
-        let cryptde = main_cryptde();
-        let http_request = "CONNECT λ:🥓:λ HTTP/1.1\r\nHost: 🥓:🥔:🥔\r\n\r\n".as_bytes();
-
-        let (hopper_mock, _hopper_awaiter, _hopper_recording_arc) = make_recorder();
-        let (neighborhood_mock, _, _neighborhood_recording_arc) = make_recorder();
-        let (dispatcher_mock, _dispatcher_awaiter, dispatcher_recording_arc) = make_recorder();
-
+    fn proxy_server_opens_socks5_tunnel_to_mail_submission_server_before_client_speaks() {
+        let main_cryptde = main_cryptde();
+        let alias_cryptde = alias_cryptde();
+        let key = alias_cryptde.public_key();
+        let (hopper_mock, hopper_awaiter, hopper_recording_arc) = make_recorder();
+        let (neighborhood_mock, _, _) = make_recorder();
         let neighborhood_mock = neighborhood_mock.route_query_response(Some(
-            zero_hop_route_response(&cryptde.public_key(), cryptde),
+            zero_hop_route_response(&main_cryptde.public_key(), main_cryptde),
         ));
-
+        let route = zero_hop_route_response(main_cryptde.public_key(), main_cryptde).route;
+        let (dispatcher_mock, _, dispatcher_recording_arc) = make_recorder();
         let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
         let stream_key = make_meaningless_stream_key();
-        let request_data = http_request.to_vec();
-        let msg_from_dispatcher = InboundClientData {
-            peer_addr: socket_addr.clone(),
-            reception_port: Some(8443),
-            sequence_number: Some(0),
+        let make_ibcd = |sequence_number: u64, data: &[u8]| InboundClientData {
+            peer_addr: socket_addr,
+            reception_port: Some(1080),
+            sequence_number: Some(sequence_number),
             last_data: false,
             is_clandestine: false,
-            data: request_data.clone(),
+            data: data.to_vec(),
         };
-
-        let stream_key_parameters_arc = Arc::new(Mutex::new(vec![]));
-        let stream_key_parameters_arc_thread = stream_key_parameters_arc.clone();
+        let greeting = make_ibcd(0, &[0x05, 0x01, 0x00]);
+        let mut connect_request = vec![0x05, 0x01, 0x00, 0x03, 16];
+        connect_request.extend(b"smtp.example.com");
+        connect_request.extend(&SMTP_SUBMISSION_PORT.to_be_bytes());
+        let connect_request = make_ibcd(1, &connect_request);
+        let expected_payload = ClientRequestPayload_0v1 {
+            stream_key,
+            sequenced_packet: SequencedPacket {
+                data: vec![],
+                sequence_number: 0,
+                last_data: false,
+            },
+            target_hostname: Some(String::from("smtp.example.com")),
+            target_port: SMTP_SUBMISSION_PORT,
+            protocol: ProxyProtocol::SMTP,
+            originator_public_key: key.clone(),
+            is_resumption: false,
+        };
+        let expected_pkg =
+            IncipientCoresPackage::new(main_cryptde, route, expected_payload.into(), &key).unwrap();
 
         thread::spawn(move || {
-            let stream_key_factory = StreamKeyFactoryMock::new()
-                .make_parameters(&stream_key_parameters_arc_thread)
-                .make_result(stream_key);
+            let stream_key_factory = StreamKeyFactoryMock::new().make_result(stream_key);
             let system = System::new(
-                "proxy_server_receives_connect_responds_with_ok_and_stores_stream_key_and_hostname",
+                "proxy_server_opens_socks5_tunnel_to_mail_submission_server_before_client_speaks",
             );
             let mut subject = ProxyServer::new(
-                cryptde,
-                alias_cryptde(),
+                main_cryptde,
+                alias_cryptde,
                 false,
                 Some(STANDARD_CONSUMING_WALLET_BALANCE),
+                Some(SocksConfig {
+                    port: 1080,
+                    credentials_opt: None,
+                }),
             );
             subject.stream_key_factory = Box::new(stream_key_factory);
             let subject_addr: Addr<ProxyServer> = subject.start();
@@ -1510,356 +3214,570 @@ mod tests {
             peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
             subject_addr.try_send(BindMessage { peer_actors }).unwrap();
 
-            subject_addr.try_send(msg_from_dispatcher).unwrap();
+            subject_addr.try_send(greeting).unwrap();
+            subject_addr.try_send(connect_request).unwrap();
             system.run();
         });
 
-        thread::sleep(Duration::from_millis(500));
+        hopper_awaiter.await_message_count(1);
+        let dispatcher_recording = dispatcher_recording_arc.lock().unwrap();
+        assert_eq!(dispatcher_recording.len(), 2);
+        let hopper_recording = hopper_recording_arc.lock().unwrap();
+        let hopper_record = hopper_recording.get_record::<IncipientCoresPackage>(0);
+        assert_eq!(hopper_record, &expected_pkg);
+    }
 
-        let expected_transmit_data_msg = TransmitDataMsg {
-            endpoint: Endpoint::Socket(socket_addr),
-            last_data: true,
-            sequence_number: Some(0),
-            data: b"HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\n\r\n".to_vec(),
+    #[test]
+    fn proxy_server_tunnels_data_that_arrives_in_the_same_read_as_the_socks5_request() {
+        let main_cryptde = main_cryptde();
+        let alias_cryptde = alias_cryptde();
+        let key = alias_cryptde.public_key();
+        let (hopper_mock, hopper_awaiter, hopper_recording_arc) = make_recorder();
+        let (neighborhood_mock, _, _) = make_recorder();
+        let neighborhood_mock = neighborhood_mock.route_query_response(Some(
+            zero_hop_route_response(&main_cryptde.public_key(), main_cryptde),
+        ));
+        let route = zero_hop_route_response(main_cryptde.public_key(), main_cryptde).route;
+        let (dispatcher_mock, _, dispatcher_recording_arc) = make_recorder();
+        let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
+        let stream_key = make_meaningless_stream_key();
+        let make_ibcd = |sequence_number: u64, data: &[u8]| InboundClientData {
+            peer_addr: socket_addr,
+            reception_port: Some(1080),
+            sequence_number: Some(sequence_number),
+            last_data: false,
+            is_clandestine: false,
+            data: data.to_vec(),
         };
+        let greeting = make_ibcd(0, &[0x05, 0x01, 0x00]);
+        let mut connect_request = vec![0x05, 0x01, 0x00, 0x03, 13];
+        connect_request.extend(b"realdomain.nu");
+        connect_request.extend(&[0x01, 0xBB]);
+        connect_request.extend(b"client hello");
+        let connect_request = make_ibcd(1, &connect_request);
+        let expected_payload = ClientRequestPayload_0v1 {
+            stream_key,
+            sequenced_packet: SequencedPacket {
+                data: b"client hello".to_vec(),
+                sequence_number: 0,
+                last_data: false,
+            },
+            target_hostname: Some(String::from("realdomain.nu")),
+            target_port: 443,
+            protocol: ProxyProtocol::TLS,
+            originator_public_key: key.clone(),
+            is_resumption: false,
+        };
+        let expected_pkg =
+            IncipientCoresPackage::new(main_cryptde, route, expected_payload.into(), &key).unwrap();
 
-        let dispatcher_recording = dispatcher_recording_arc.lock().unwrap();
-        let record = dispatcher_recording.get_record::<TransmitDataMsg>(0);
+        thread::spawn(move || {
+            let stream_key_factory = StreamKeyFactoryMock::new().make_result(stream_key);
+            let system = System::new(
+                "proxy_server_tunnels_data_that_arrives_in_the_same_read_as_the_socks5_request",
+            );
+            let mut subject = ProxyServer::new(
+                main_cryptde,
+                alias_cryptde,
+                false,
+                Some(STANDARD_CONSUMING_WALLET_BALANCE),
+                Some(SocksConfig {
+                    port: 1080,
+                    credentials_opt: None,
+                }),
+            );
+            subject.stream_key_factory = Box::new(stream_key_factory);
+            let subject_addr: Addr<ProxyServer> = subject.start();
+            let mut peer_actors = peer_actors_builder()
+                .dispatcher(dispatcher_mock)
+                .hopper(hopper_mock)
+                .neighborhood(neighborhood_mock)
+                .build();
+            peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
+            subject_addr.try_send(BindMessage { peer_actors }).unwrap();
 
-        assert_eq!(&expected_transmit_data_msg, record);
+            subject_addr.try_send(greeting).unwrap();
+            subject_addr.try_send(connect_request).unwrap();
+            system.run();
+        });
+
+        hopper_awaiter.await_message_count(1);
+        let dispatcher_recording = dispatcher_recording_arc.lock().unwrap();
+        assert_eq!(dispatcher_recording.len(), 2);
+        let hopper_recording = hopper_recording_arc.lock().unwrap();
+        let hopper_record = hopper_recording.get_record::<IncipientCoresPackage>(0);
+        assert_eq!(hopper_record, &expected_pkg);
     }
 
     #[test]
-    fn proxy_server_receives_http_request_with_no_consuming_wallet_and_sends_impersonated_response()
-    {
-        init_test_logging();
-        let cryptde = main_cryptde();
-        let http_request = b"GET /index.html HTTP/1.1\r\nHost: nowhere.com\r\n\r\n";
-        let (hopper, _, hopper_log_arc) = make_recorder();
-        let (neighborhood, _, neighborhood_log_arc) = make_recorder();
-        let (dispatcher, _, dispatcher_log_arc) = make_recorder();
+    fn proxy_server_associates_socks5_client_and_relays_its_datagrams_to_hopper() {
+        let main_cryptde = main_cryptde();
+        let alias_cryptde = alias_cryptde();
+        let key = alias_cryptde.public_key();
+        let (hopper_mock, hopper_awaiter, hopper_recording_arc) = make_recorder();
+        let (neighborhood_mock, _, _) = make_recorder();
+        let neighborhood_mock = neighborhood_mock.route_query_response(Some(
+            zero_hop_route_response(&main_cryptde.public_key(), main_cryptde),
+        ));
+        let route = zero_hop_route_response(main_cryptde.public_key(), main_cryptde).route;
+        let (dispatcher_mock, _, dispatcher_recording_arc) = make_recorder();
+        let make_parameters_arc = Arc::new(Mutex::new(vec![]));
         let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
+        let client_udp_addr = SocketAddr::from_str("127.0.0.1:5555").unwrap();
         let stream_key = make_meaningless_stream_key();
-        let expected_data = http_request.to_vec();
-        let msg_from_dispatcher = InboundClientData {
-            peer_addr: socket_addr.clone(),
-            reception_port: Some(HTTP_PORT),
-            sequence_number: Some(0),
-            last_data: true,
+        let make_ibcd = |sequence_number: u64, data: &[u8]| InboundClientData {
+            peer_addr: socket_addr,
+            reception_port: Some(1080),
+            sequence_number: Some(sequence_number),
+            last_data: false,
             is_clandestine: false,
-            data: expected_data.clone(),
+            data: data.to_vec(),
         };
-        let stream_key_factory = StreamKeyFactoryMock::new(); // can't make any stream keys; shouldn't have to
-        let system = System::new("proxy_server_receives_http_request_with_no_consuming_wallet_and_sends_impersonated_response");
-        let mut subject = ProxyServer::new(cryptde, alias_cryptde(), true, None);
-        subject.stream_key_factory = Box::new(stream_key_factory);
-        subject.keys_and_addrs.insert(stream_key, socket_addr);
-        let subject_addr: Addr<ProxyServer> = subject.start();
-        let mut peer_actors = peer_actors_builder()
-            .dispatcher(dispatcher)
-            .hopper(hopper)
-            .neighborhood(neighborhood)
-            .build();
-        peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
-        subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+        let greeting = make_ibcd(0, &[0x05, 0x01, 0x00]);
+        let associate_request = make_ibcd(
+            1,
+            &[0x05, 0x03, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x15, 0xB3],
+        );
+        let mut datagram = vec![0x00, 0x00, 0x00, 0x03, 13];
+        datagram.extend(b"realdomain.nu");
+        datagram.extend(&[0x01, 0xBB]);
+        datagram.extend(b"quic hello");
+        let expected_payload = ClientUdpRequestPayload_0v1 {
+            stream_key,
+            target_hostname: "realdomain.nu".to_string(),
+            target_port: 443,
+            data: PlainData::new(b"quic hello"),
+            originator_public_key: key.clone(),
+        };
+        let expected_pkg =
+            IncipientCoresPackage::new(main_cryptde, route, expected_payload.into(), &key).unwrap();
+        let make_parameters_inner_arc = make_parameters_arc.clone();
 
-        subject_addr.try_send(msg_from_dispatcher).unwrap();
+        thread::spawn(move || {
+            let stream_key_factory = StreamKeyFactoryMock::new().make_result(stream_key);
+            let udp_relay_factory = UdpRelayFactoryMock::new()
+                .make_parameters(&make_parameters_inner_arc)
+                .make_result(Ok(Box::new(UdpRelayMock {
+                    send_to_params: Arc::new(Mutex::new(vec![])),
+                })));
+            let system = System::new(
+                "proxy_server_associates_socks5_client_and_relays_its_datagrams_to_hopper",
+            );
+            let mut subject = ProxyServer::new(
+                main_cryptde,
+                alias_cryptde,
+                false,
+                Some(STANDARD_CONSUMING_WALLET_BALANCE),
+                Some(SocksConfig {
+                    port: 1080,
+                    credentials_opt: None,
+                }),
+            );
+            subject.stream_key_factory = Box::new(stream_key_factory);
+            subject.udp_relay_factory = Box::new(udp_relay_factory);
+            let subject_addr: Addr<ProxyServer> = subject.start();
+            let mut peer_actors = peer_actors_builder()
+                .dispatcher(dispatcher_mock)
+                .hopper(hopper_mock)
+                .neighborhood(neighborhood_mock)
+                .build();
+            peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
+            subject_addr.try_send(BindMessage { peer_actors }).unwrap();
 
-        System::current().stop();
-        system.run();
-        let neighborhood_recording = neighborhood_log_arc.lock().unwrap();
-        assert!(neighborhood_recording.is_empty());
-        let hopper_recording = hopper_log_arc.lock().unwrap();
-        assert!(hopper_recording.is_empty());
-        let dispatcher_recording = dispatcher_log_arc.lock().unwrap();
-        let record = dispatcher_recording.get_record::<TransmitDataMsg>(0);
-        let server_impersonator = ServerImpersonatorHttp {};
+            subject_addr.try_send(greeting).unwrap();
+            subject_addr.try_send(associate_request).unwrap();
+            subject_addr
+                .try_send(InboundClientDatagram {
+                    peer_addr: client_udp_addr,
+                    data: datagram,
+                })
+                .unwrap();
+            system.run();
+        });
+
+        hopper_awaiter.await_message_count(1);
+        assert_eq!(*make_parameters_arc.lock().unwrap(), vec![1080]);
+        let dispatcher_recording = dispatcher_recording_arc.lock().unwrap();
         assert_eq!(
-            record,
+            dispatcher_recording.get_record::<TransmitDataMsg>(1),
             &TransmitDataMsg {
                 endpoint: Endpoint::Socket(socket_addr),
-                last_data: true,
-                sequence_number: Some(0),
-                data: server_impersonator.consuming_wallet_absent(),
+                last_data: false,
+                sequence_number: Some(1),
+                data: vec![0x05, 0x00, 0x00, 0x01, 0x7F, 0x00, 0x00, 0x01, 0x04, 0x38],
+                relayed_from_opt: None,
             }
         );
-        TestLogHandler::new().exists_log_containing(
-            "ERROR: ProxyServer: Browser request rejected due to missing consuming wallet",
-        );
+        let hopper_recording = hopper_recording_arc.lock().unwrap();
+        let hopper_record = hopper_recording.get_record::<IncipientCoresPackage>(0);
+        assert_eq!(hopper_record, &expected_pkg);
     }
 
     #[test]
-    fn proxy_server_receives_tls_request_with_no_consuming_wallet_and_sends_impersonated_response()
-    {
+    fn proxy_server_refuses_udp_associate_if_udp_relay_could_not_be_opened() {
         init_test_logging();
-        let cryptde = main_cryptde();
-        let tls_request = b"Fake TLS request";
-        let (hopper, _, hopper_log_arc) = make_recorder();
-        let (neighborhood, _, neighborhood_log_arc) = make_recorder();
-        let (dispatcher, _, dispatcher_log_arc) = make_recorder();
+        let system =
+            System::new("proxy_server_refuses_udp_associate_if_udp_relay_could_not_be_opened");
+        let (dispatcher_mock, _, dispatcher_recording_arc) = make_recorder();
         let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
-        let stream_key = make_meaningless_stream_key();
-        let expected_data = tls_request.to_vec();
-        let msg_from_dispatcher = InboundClientData {
-            peer_addr: socket_addr.clone(),
-            reception_port: Some(TLS_PORT),
-            sequence_number: Some(0),
-            last_data: true,
+        let make_ibcd = |sequence_number: u64, data: &[u8]| InboundClientData {
+            peer_addr: socket_addr,
+            reception_port: Some(1080),
+            sequence_number: Some(sequence_number),
+            last_data: false,
             is_clandestine: false,
-            data: expected_data.clone(),
+            data: data.to_vec(),
         };
-        let stream_key_factory = StreamKeyFactoryMock::new(); // can't make any stream keys; shouldn't have to
-        let system = System::new("proxy_server_receives_tls_request_with_no_consuming_wallet_and_sends_impersonated_response");
-        let mut subject = ProxyServer::new(cryptde, alias_cryptde(), true, None);
-        subject.stream_key_factory = Box::new(stream_key_factory);
-        subject.keys_and_addrs.insert(stream_key, socket_addr);
+        let mut subject = ProxyServer::new(
+            main_cryptde(),
+            alias_cryptde(),
+            false,
+            Some(STANDARD_CONSUMING_WALLET_BALANCE),
+            Some(SocksConfig {
+                port: 1080,
+                credentials_opt: None,
+            }),
+        );
+        subject.stream_key_factory =
+            Box::new(StreamKeyFactoryMock::new().make_result(make_meaningless_stream_key()));
+        subject.udp_relay_factory = Box::new(
+            UdpRelayFactoryMock::new().make_result(Err(io::Error::from(io::ErrorKind::AddrInUse))),
+        );
         let subject_addr: Addr<ProxyServer> = subject.start();
-        let mut peer_actors = peer_actors_builder()
-            .dispatcher(dispatcher)
-            .hopper(hopper)
-            .neighborhood(neighborhood)
-            .build();
+        let mut peer_actors = peer_actors_builder().dispatcher(dispatcher_mock).build();
         peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
         subject_addr.try_send(BindMessage { peer_actors }).unwrap();
 
-        subject_addr.try_send(msg_from_dispatcher).unwrap();
+        subject_addr
+            .try_send(make_ibcd(0, &[0x05, 0x01, 0x00]))
+            .unwrap();
+        subject_addr
+            .try_send(make_ibcd(
+                1,
+                &[0x05, 0x03, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+            ))
+            .unwrap();
 
-        System::current().stop();
+        System::current().stop_with_code(0);
         system.run();
-        let neighborhood_recording = neighborhood_log_arc.lock().unwrap();
-        assert!(neighborhood_recording.is_empty());
-        let hopper_recording = hopper_log_arc.lock().unwrap();
-        assert!(hopper_recording.is_empty());
-        let dispatcher_recording = dispatcher_log_arc.lock().unwrap();
-        let record = dispatcher_recording.get_record::<TransmitDataMsg>(0);
-        let server_impersonator = ServerImpersonatorTls {};
-        assert_eq!(
-            record,
-            &TransmitDataMsg {
-                endpoint: Endpoint::Socket(socket_addr),
-                last_data: true,
-                sequence_number: Some(0),
-                data: server_impersonator.consuming_wallet_absent(),
-            }
-        );
+        let dispatcher_recording = dispatcher_recording_arc.lock().unwrap();
+        let record = dispatcher_recording.get_record::<TransmitDataMsg>(1);
+        assert_eq!(record.last_data, true);
+        assert_eq!(record.data[1], 0x07);
         TestLogHandler::new().exists_log_containing(
-            "ERROR: ProxyServer: Browser request rejected due to missing consuming wallet",
+            "ERROR: ProxyServer: Could not open SOCKS UDP relay on port 1080",
         );
     }
 
     #[test]
-    fn proxy_server_receives_http_request_with_no_consuming_wallet_in_zero_hop_mode_and_handles_normally(
-    ) {
+    fn proxy_server_discards_datagrams_from_clients_without_udp_association() {
         init_test_logging();
-        let main_cryptde = main_cryptde();
-        let alias_cryptde = alias_cryptde();
-        let expected_data = b"GET /index.html HTTP/1.1\r\nHost: nowhere.com\r\n\r\n".to_vec();
-        let expected_data_inner = expected_data.clone();
-        let expected_route = zero_hop_route_response(main_cryptde.public_key(), main_cryptde);
+        let system =
+            System::new("proxy_server_discards_datagrams_from_clients_without_udp_association");
+        let (hopper_mock, _, hopper_recording_arc) = make_recorder();
+        let subject = ProxyServer::new(
+            main_cryptde(),
+            alias_cryptde(),
+            false,
+            Some(STANDARD_CONSUMING_WALLET_BALANCE),
+            None,
+        );
+        let subject_addr: Addr<ProxyServer> = subject.start();
+        let mut peer_actors = peer_actors_builder().hopper(hopper_mock).build();
+        peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
+        subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+
+        subject_addr
+            .try_send(InboundClientDatagram {
+                peer_addr: SocketAddr::from_str("127.0.0.1:5556").unwrap(),
+                data: vec![0x00, 0x00, 0x00, 0x01, 1, 2, 3, 4, 0x01, 0xBB],
+            })
+            .unwrap();
+
+        System::current().stop_with_code(0);
+        system.run();
+        assert_eq!(hopper_recording_arc.lock().unwrap().len(), 0);
+        TestLogHandler::new().exists_log_containing(
+            "WARN: ProxyServer: Discarding 10-byte datagram from 127.0.0.1:5556: no SOCKS client has a UDP association for it",
+        );
+    }
+
+    #[test]
+    fn proxy_server_relays_udp_responses_to_associated_socks5_client() {
+        let system = System::new("proxy_server_relays_udp_responses_to_associated_socks5_client");
+        let (accountant, _, accountant_recording_arc) = make_recorder();
+        let send_to_params_arc = Arc::new(Mutex::new(vec![]));
+        let cryptde = main_cryptde();
+        let mut subject = ProxyServer::new(
+            cryptde,
+            alias_cryptde(),
+            false,
+            Some(STANDARD_CONSUMING_WALLET_BALANCE),
+            None,
+        );
+        let client_udp_addr = SocketAddr::from_str("127.0.0.1:5555").unwrap();
         let stream_key = make_meaningless_stream_key();
-        let (hopper, hopper_awaiter, hopper_log_arc) = make_recorder();
-        let neighborhood = Recorder::new().route_query_response(Some(expected_route.clone()));
-        let neighborhood_log_arc = neighborhood.get_recording();
-        let (dispatcher, _, dispatcher_log_arc) = make_recorder();
-        thread::spawn(move || {
-            let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
-            let msg_from_dispatcher = InboundClientData {
-                peer_addr: socket_addr.clone(),
-                reception_port: Some(HTTP_PORT),
-                sequence_number: Some(0),
-                last_data: true,
-                is_clandestine: false,
-                data: expected_data_inner,
-            };
-            let stream_key_factory = StreamKeyFactoryMock::new(); // can't make any stream keys; shouldn't have to
-            let system = System::new("proxy_server_receives_http_request_with_no_consuming_wallet_in_zero_hop_mode_and_handles_normally");
-            let mut subject = ProxyServer::new(main_cryptde, alias_cryptde, false, None);
-            subject.stream_key_factory = Box::new(stream_key_factory);
-            subject.keys_and_addrs.insert(stream_key, socket_addr);
-            let subject_addr: Addr<ProxyServer> = subject.start();
-            let mut peer_actors = peer_actors_builder()
-                .dispatcher(dispatcher)
-                .hopper(hopper)
-                .neighborhood(neighborhood)
-                .build();
-            peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
-            subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+        subject.udp_relay_opt = Some(Box::new(UdpRelayMock {
+            send_to_params: send_to_params_arc.clone(),
+        }));
+        subject.udp_associations.insert(
+            stream_key,
+            UdpAssociation {
+                client_port: 5555,
+                client_addr_opt: Some(client_udp_addr),
+            },
+        );
+        let exit_wallet = make_wallet("exit");
+        subject.route_ids_to_return_routes.insert(
+            1234,
+            AddReturnRouteMessage {
+                return_route_id: 1234,
+                expected_services: vec![
+                    ExpectedService::Exit(
+                        PublicKey::from(&b"irrelevant"[..]),
+                        exit_wallet.clone(),
+                        rate_pack(101),
+                    ),
+                    ExpectedService::Nothing,
+                ],
+                protocol: ProxyProtocol::TLS,
+                server_name: Some("realdomain.nu".to_string()),
+            },
+        );
+        let subject_addr: Addr<ProxyServer> = subject.start();
+        let expired_cores_package: ExpiredCoresPackage<ClientUdpResponsePayload_0v1> =
+            ExpiredCoresPackage::new(
+                SocketAddr::from_str("1.2.3.4:1234").unwrap(),
+                Some(make_wallet("irrelevant")),
+                return_route_with_id(cryptde, 1234),
+                ClientUdpResponsePayload_0v1 {
+                    stream_key,
+                    source: SocketAddr::from_str("5.6.7.8:443").unwrap(),
+                    data: PlainData::new(b"quic reply"),
+                },
+                0,
+            );
+        let mut peer_actors = peer_actors_builder().accountant(accountant).build();
+        peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
+        subject_addr.try_send(BindMessage { peer_actors }).unwrap();
 
-            subject_addr.try_send(msg_from_dispatcher).unwrap();
+        subject_addr.try_send(expired_cores_package).unwrap();
 
-            system.run();
-        });
-        hopper_awaiter.await_message_count(1);
-        let neighborhood_recording = neighborhood_log_arc.lock().unwrap();
+        System::current().stop_with_code(0);
+        system.run();
+        let mut expected_datagram = vec![0x00, 0x00, 0x00, 0x01, 5, 6, 7, 8, 0x01, 0xBB];
+        expected_datagram.extend(b"quic reply");
         assert_eq!(
-            neighborhood_recording.get_record::<RouteQueryMessage>(0),
-            &RouteQueryMessage {
-                target_key_opt: None,
-                target_component: Component::ProxyClient,
-                minimum_hop_count: 0,
-                return_component_opt: Some(Component::ProxyServer)
-            }
+            *send_to_params_arc.lock().unwrap(),
+            vec![(expected_datagram, client_udp_addr)]
         );
-        let dispatcher_recording = dispatcher_log_arc.lock().unwrap();
-        assert!(dispatcher_recording.is_empty());
-        let hopper_recording = hopper_log_arc.lock().unwrap();
+        let accountant_recording = accountant_recording_arc.lock().unwrap();
         assert_eq!(
-            hopper_recording.get_record::<IncipientCoresPackage>(0),
-            &IncipientCoresPackage::new(
-                main_cryptde,
-                expected_route.route,
-                MessageType::ClientRequest(VersionedData::new(
-                    &crate::sub_lib::migrations::client_request_payload::MIGRATIONS,
-                    &ClientRequestPayload_0v1 {
-                        stream_key,
-                        sequenced_packet: SequencedPacket::new(expected_data, 0, true),
-                        target_hostname: Some("nowhere.com".to_string()),
-                        target_port: 80,
-                        protocol: ProxyProtocol::HTTP,
-                        originator_public_key: alias_cryptde.public_key().clone(),
-                    }
-                )),
-                alias_cryptde.public_key()
-            )
-            .unwrap()
+            accountant_recording.get_record::<ReportExitServiceConsumedMessage>(0),
+            &ReportExitServiceConsumedMessage {
+                earning_wallet: exit_wallet,
+                payload_size: 10,
+                service_rate: DEFAULT_RATE_PACK.exit_service_rate,
+                byte_rate: DEFAULT_RATE_PACK.exit_byte_rate,
+            }
         );
     }
 
     #[test]
-    fn proxy_server_receives_tls_request_with_no_consuming_wallet_in_zero_hop_mode_and_handles_normally(
-    ) {
-        init_test_logging();
+    fn proxy_server_refuses_socks5_client_with_wrong_credentials_and_closes_stream() {
         let main_cryptde = main_cryptde();
         let alias_cryptde = alias_cryptde();
-        let expected_data = b"Fake TLS request".to_vec();
-        let expected_data_inner = expected_data.clone();
-        let expected_route = zero_hop_route_response(main_cryptde.public_key(), main_cryptde);
-        let stream_key = make_meaningless_stream_key();
-        let (hopper, hopper_awaiter, hopper_log_arc) = make_recorder();
-        let neighborhood = Recorder::new().route_query_response(Some(expected_route.clone()));
-        let neighborhood_log_arc = neighborhood.get_recording();
-        let (dispatcher, _, dispatcher_log_arc) = make_recorder();
+        let (dispatcher_mock, dispatcher_awaiter, dispatcher_recording_arc) = make_recorder();
+        let (hopper_mock, _, hopper_recording_arc) = make_recorder();
+        let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
+        let make_ibcd = |sequence_number: u64, data: &[u8]| InboundClientData {
+            peer_addr: socket_addr,
+            reception_port: Some(1080),
+            sequence_number: Some(sequence_number),
+            last_data: false,
+            is_clandestine: false,
+            data: data.to_vec(),
+        };
+        let greeting = make_ibcd(0, &[0x05, 0x01, 0x02]);
+        let credentials = make_ibcd(1, &[0x01, 0x05, b'b', b'o', b'o', b'g', b'a', 0x01, b'x']);
+
         thread::spawn(move || {
-            let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
-            let msg_from_dispatcher = InboundClientData {
-                peer_addr: socket_addr.clone(),
-                reception_port: Some(TLS_PORT),
-                sequence_number: Some(0),
-                last_data: true,
-                is_clandestine: false,
-                data: expected_data_inner,
-            };
-            let stream_key_factory = StreamKeyFactoryMock::new(); // can't make any stream keys; shouldn't have to
-            let system = System::new("proxy_server_receives_tls_request_with_no_consuming_wallet_in_zero_hop_mode_and_handles_normally");
-            let mut subject = ProxyServer::new(main_cryptde, alias_cryptde, false, None);
-            subject.stream_key_factory = Box::new(stream_key_factory);
-            subject.keys_and_addrs.insert(stream_key, socket_addr);
+            let system = System::new(
+                "proxy_server_refuses_socks5_client_with_wrong_credentials_and_closes_stream",
+            );
+            let subject = ProxyServer::new(
+                main_cryptde,
+                alias_cryptde,
+                false,
+                Some(STANDARD_CONSUMING_WALLET_BALANCE),
+                Some(SocksConfig {
+                    port: 1080,
+                    credentials_opt: Some(SocksCredentials {
+                        username: "booga".to_string(),
+                        password: "agoob".to_string(),
+                    }),
+                }),
+            );
             let subject_addr: Addr<ProxyServer> = subject.start();
             let mut peer_actors = peer_actors_builder()
-                .dispatcher(dispatcher)
-                .hopper(hopper)
-                .neighborhood(neighborhood)
+                .dispatcher(dispatcher_mock)
+                .hopper(hopper_mock)
                 .build();
             peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
             subject_addr.try_send(BindMessage { peer_actors }).unwrap();
 
-            subject_addr.try_send(msg_from_dispatcher).unwrap();
-
+            subject_addr.try_send(greeting).unwrap();
+            subject_addr.try_send(credentials).unwrap();
             system.run();
         });
-        hopper_awaiter.await_message_count(1);
-        let neighborhood_recording = neighborhood_log_arc.lock().unwrap();
+
+        dispatcher_awaiter.await_message_count(2);
+        let dispatcher_recording = dispatcher_recording_arc.lock().unwrap();
         assert_eq!(
-            neighborhood_recording.get_record::<RouteQueryMessage>(0),
-            &RouteQueryMessage {
-                target_key_opt: None,
-                target_component: Component::ProxyClient,
-                minimum_hop_count: 0,
-                return_component_opt: Some(Component::ProxyServer)
+            dispatcher_recording.get_record::<TransmitDataMsg>(0),
+            &TransmitDataMsg {
+                endpoint: Endpoint::Socket(socket_addr),
+                last_data: false,
+                sequence_number: Some(0),
+                data: vec![0x05, 0x02],
+                relayed_from_opt: None,
             }
         );
-        let dispatcher_recording = dispatcher_log_arc.lock().unwrap();
-        assert!(dispatcher_recording.is_empty());
-        let hopper_recording = hopper_log_arc.lock().unwrap();
         assert_eq!(
-            hopper_recording.get_record::<IncipientCoresPackage>(0),
-            &IncipientCoresPackage::new(
-                main_cryptde,
-                expected_route.route,
-                MessageType::ClientRequest(VersionedData::new(
-                    &crate::sub_lib::migrations::client_request_payload::MIGRATIONS,
-                    &ClientRequestPayload_0v1 {
-                        stream_key,
-                        sequenced_packet: SequencedPacket::new(expected_data, 0, true),
-                        target_hostname: None,
-                        target_port: 443,
-                        protocol: ProxyProtocol::TLS,
-                        originator_public_key: alias_cryptde.public_key().clone(),
-                    }
-                ),),
-                alias_cryptde.public_key()
-            )
-            .unwrap()
+            dispatcher_recording.get_record::<TransmitDataMsg>(1),
+            &TransmitDataMsg {
+                endpoint: Endpoint::Socket(socket_addr),
+                last_data: true,
+                sequence_number: Some(1),
+                data: vec![0x01, 0x01],
+                relayed_from_opt: None,
+            }
         );
+        assert_eq!(hopper_recording_arc.lock().unwrap().len(), 0);
     }
 
     #[test]
-    fn proxy_server_receives_http_request_with_existing_stream_key_from_dispatcher_then_sends_cores_package_to_hopper(
+    fn handle_client_response_payload_increments_sequence_number_when_browser_proxy_sequence_offset_is_true(
     ) {
-        let main_cryptde = main_cryptde();
-        let alias_cryptde = alias_cryptde();
-        let http_request = b"GET /index.html HTTP/1.1\r\nHost: nowhere.com\r\n\r\n";
-        let hopper_mock = Recorder::new();
-        let hopper_log_arc = hopper_mock.get_recording();
-        let hopper_awaiter = hopper_mock.get_awaiter();
-        let neighborhood_mock = Recorder::new().route_query_response(Some(
-            zero_hop_route_response(&main_cryptde.public_key(), main_cryptde),
-        ));
+        let system = System::new("handle_client_response_payload_increments_sequence_number_when_browser_proxy_sequence_offset_is_true");
+        let (dispatcher_mock, _, dispatcher_log_arc) = make_recorder();
+        let cryptde = main_cryptde();
+        let mut subject = ProxyServer::new(
+            cryptde,
+            alias_cryptde(),
+            false,
+            Some(STANDARD_CONSUMING_WALLET_BALANCE),
+            None,
+        );
         let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
         let stream_key = make_meaningless_stream_key();
-        let expected_data = http_request.to_vec();
-        let msg_from_dispatcher = InboundClientData {
-            peer_addr: socket_addr.clone(),
-            reception_port: Some(HTTP_PORT),
-            sequence_number: Some(0),
-            last_data: true,
+        subject
+            .keys_and_addrs
+            .insert(stream_key.clone(), socket_addr.clone());
+
+        subject.route_ids_to_return_routes.insert(
+            1234,
+            AddReturnRouteMessage {
+                return_route_id: 1234,
+                expected_services: vec![ExpectedService::Nothing],
+                protocol: ProxyProtocol::TLS,
+                server_name: None,
+            },
+        );
+        let subject_addr: Addr<ProxyServer> = subject.start();
+        let http_request = b"CONNECT https://realdomain.nu:443 HTTP/1.1\r\nHost: https://bunkjunk.wrong:443\r\n\r\n";
+        let request_data = http_request.to_vec();
+        let inbound_client_data = InboundClientData {
+            peer_addr: socket_addr,
+            reception_port: Some(443),
+            last_data: false,
             is_clandestine: false,
-            data: expected_data.clone(),
+            sequence_number: Some(0),
+            data: request_data,
         };
-        let expected_http_request = PlainData::new(http_request);
-        let route = zero_hop_route_response(main_cryptde.public_key(), main_cryptde).route;
-        let expected_payload = ClientRequestPayload_0v1 {
-            stream_key: stream_key.clone(),
+
+        let client_response_payload = ClientResponsePayload_0v1 {
+            stream_key,
             sequenced_packet: SequencedPacket {
-                data: expected_http_request.into(),
+                data: b"some data".to_vec(),
                 sequence_number: 0,
-                last_data: true,
+                last_data: false,
             },
-            target_hostname: Some(String::from("nowhere.com")),
-            target_port: HTTP_PORT,
-            protocol: ProxyProtocol::HTTP,
-            originator_public_key: alias_cryptde.public_key().clone(),
         };
-        let expected_pkg = IncipientCoresPackage::new(
-            main_cryptde,
-            route.clone(),
-            expected_payload.into(),
-            alias_cryptde.public_key(),
-        )
-        .unwrap();
+
+        let expired_cores_package: ExpiredCoresPackage<ClientResponsePayload_0v1> =
+            ExpiredCoresPackage::new(
+                SocketAddr::from_str("1.2.3.4:1234").unwrap(),
+                Some(make_wallet("irrelevant")),
+                return_route_with_id(cryptde, 1234),
+                client_response_payload.into(),
+                0,
+            );
+
+        let mut peer_actors = peer_actors_builder().dispatcher(dispatcher_mock).build();
+        peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
+        subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+        subject_addr.try_send(inbound_client_data).unwrap();
+
+        subject_addr
+            .try_send(expired_cores_package.clone())
+            .unwrap();
+
+        System::current().stop();
+        system.run();
+
+        let dispatcher_recording = dispatcher_log_arc.lock().unwrap();
+        let record = dispatcher_recording.get_record::<TransmitDataMsg>(1);
+
+        assert_eq!(record.sequence_number.unwrap(), 1);
+    }
+
+    #[test]
+    fn proxy_server_sends_route_failure_for_connect_requests_to_ports_other_than_443() {
+        let cryptde = main_cryptde();
+        let http_request = b"CONNECT https://realdomain.nu:8443 HTTP/1.1\r\nHost: https://bunkjunk.wrong:443\r\n\r\n";
+
+        let (hopper_mock, _hopper_awaiter, _hopper_recording_arc) = make_recorder();
+        let (neighborhood_mock, _, _neighborhood_recording_arc) = make_recorder();
+        let (dispatcher_mock, _dispatcher_awaiter, dispatcher_recording_arc) = make_recorder();
+
+        let neighborhood_mock = neighborhood_mock.route_query_response(Some(
+            zero_hop_route_response(&cryptde.public_key(), cryptde),
+        ));
+
+        let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
+        let stream_key = make_meaningless_stream_key();
+        let request_data = http_request.to_vec();
+        let msg_from_dispatcher = InboundClientData {
+            peer_addr: socket_addr.clone(),
+            reception_port: Some(8443),
+            sequence_number: Some(0),
+            last_data: false,
+            is_clandestine: false,
+            data: request_data.clone(),
+        };
+
+        let stream_key_parameters_arc = Arc::new(Mutex::new(vec![]));
+        let stream_key_parameters_arc_thread = stream_key_parameters_arc.clone();
+
         thread::spawn(move || {
-            let stream_key_factory = StreamKeyFactoryMock::new(); // can't make any stream keys; shouldn't have to
-            let system = System::new("proxy_server_receives_http_request_from_dispatcher_then_sends_cores_package_to_hopper");
+            let stream_key_factory = StreamKeyFactoryMock::new()
+                .make_parameters(&stream_key_parameters_arc_thread)
+                .make_result(stream_key);
+            let system = System::new(
+                "proxy_server_receives_connect_responds_with_ok_and_stores_stream_key_and_hostname",
+            );
             let mut subject = ProxyServer::new(
-                main_cryptde,
-                alias_cryptde,
+                cryptde,
+                alias_cryptde(),
                 false,
                 Some(STANDARD_CONSUMING_WALLET_BALANCE),
+                None,
             );
             subject.stream_key_factory = Box::new(stream_key_factory);
-            subject.keys_and_addrs.insert(stream_key, socket_addr);
             let subject_addr: Addr<ProxyServer> = subject.start();
             let mut peer_actors = peer_actors_builder()
+                .dispatcher(dispatcher_mock)
                 .hopper(hopper_mock)
                 .neighborhood(neighborhood_mock)
                 .build();
@@ -1867,145 +3785,106 @@ mod tests {
             subject_addr.try_send(BindMessage { peer_actors }).unwrap();
 
             subject_addr.try_send(msg_from_dispatcher).unwrap();
-
             system.run();
         });
 
-        hopper_awaiter.await_message_count(1);
-        let recording = hopper_log_arc.lock().unwrap();
-        let record = recording.get_record::<IncipientCoresPackage>(0);
-        assert_eq!(record, &expected_pkg);
+        thread::sleep(Duration::from_millis(500));
+
+        let expected_transmit_data_msg = TransmitDataMsg {
+            endpoint: Endpoint::Socket(socket_addr),
+            last_data: true,
+            sequence_number: Some(0),
+            data: b"HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\n\r\n".to_vec(),
+            relayed_from_opt: None,
+        };
+
+        let dispatcher_recording = dispatcher_recording_arc.lock().unwrap();
+        let record = dispatcher_recording.get_record::<TransmitDataMsg>(0);
+
+        assert_eq!(record, &expected_transmit_data_msg);
     }
 
     #[test]
-    fn proxy_server_applies_late_wallet_information() {
-        let main_cryptde = main_cryptde();
-        let alias_cryptde = alias_cryptde();
-        let http_request = b"GET /index.html HTTP/1.1\r\nHost: nowhere.com\r\n\r\n";
-        let hopper_mock = Recorder::new();
-        let hopper_log_arc = hopper_mock.get_recording();
-        let hopper_awaiter = hopper_mock.get_awaiter();
-        let neighborhood_mock = Recorder::new().route_query_response(Some(
-            zero_hop_route_response(&main_cryptde.public_key(), main_cryptde),
+    fn proxy_server_sends_error_and_shuts_down_stream_when_connect_host_unparseable() {
+        let cryptde = main_cryptde();
+        let http_request = "CONNECT λ:🥓:λ HTTP/1.1\r\nHost: 🥓:🥔:🥔\r\n\r\n".as_bytes();
+
+        let (hopper_mock, _hopper_awaiter, _hopper_recording_arc) = make_recorder();
+        let (neighborhood_mock, _, _neighborhood_recording_arc) = make_recorder();
+        let (dispatcher_mock, _dispatcher_awaiter, dispatcher_recording_arc) = make_recorder();
+
+        let neighborhood_mock = neighborhood_mock.route_query_response(Some(
+            zero_hop_route_response(&cryptde.public_key(), cryptde),
         ));
+
         let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
         let stream_key = make_meaningless_stream_key();
-        let expected_data = http_request.to_vec();
+        let request_data = http_request.to_vec();
         let msg_from_dispatcher = InboundClientData {
             peer_addr: socket_addr.clone(),
-            reception_port: Some(HTTP_PORT),
+            reception_port: Some(8443),
             sequence_number: Some(0),
-            last_data: true,
+            last_data: false,
             is_clandestine: false,
-            data: expected_data.clone(),
-        };
-        let expected_http_request = PlainData::new(http_request);
-        let route = zero_hop_route_response(main_cryptde.public_key(), main_cryptde).route;
-        let expected_payload = ClientRequestPayload_0v1 {
-            stream_key: stream_key.clone(),
-            sequenced_packet: SequencedPacket {
-                data: expected_http_request.into(),
-                sequence_number: 0,
-                last_data: true,
-            },
-            target_hostname: Some(String::from("nowhere.com")),
-            target_port: HTTP_PORT,
-            protocol: ProxyProtocol::HTTP,
-            originator_public_key: alias_cryptde.public_key().clone(),
+            data: request_data.clone(),
         };
-        let expected_pkg = IncipientCoresPackage::new(
-            main_cryptde,
-            route.clone(),
-            expected_payload.into(),
-            alias_cryptde.public_key(),
-        )
-        .unwrap();
+
+        let stream_key_parameters_arc = Arc::new(Mutex::new(vec![]));
+        let stream_key_parameters_arc_thread = stream_key_parameters_arc.clone();
+
         thread::spawn(move || {
-            let stream_key_factory = StreamKeyFactoryMock::new(); // can't make any stream keys; shouldn't have to
-            let system = System::new("proxy_server_applies_late_wallet_information");
-            let mut subject = ProxyServer::new(main_cryptde, alias_cryptde, false, None);
+            let stream_key_factory = StreamKeyFactoryMock::new()
+                .make_parameters(&stream_key_parameters_arc_thread)
+                .make_result(stream_key);
+            let system = System::new(
+                "proxy_server_receives_connect_responds_with_ok_and_stores_stream_key_and_hostname",
+            );
+            let mut subject = ProxyServer::new(
+                cryptde,
+                alias_cryptde(),
+                false,
+                Some(STANDARD_CONSUMING_WALLET_BALANCE),
+                None,
+            );
             subject.stream_key_factory = Box::new(stream_key_factory);
-            subject.keys_and_addrs.insert(stream_key, socket_addr);
             let subject_addr: Addr<ProxyServer> = subject.start();
             let mut peer_actors = peer_actors_builder()
+                .dispatcher(dispatcher_mock)
                 .hopper(hopper_mock)
                 .neighborhood(neighborhood_mock)
                 .build();
             peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
             subject_addr.try_send(BindMessage { peer_actors }).unwrap();
 
-            subject_addr
-                .try_send(SetConsumingWalletMessage {
-                    wallet: make_wallet("Consuming wallet"),
-                })
-                .unwrap();
-
             subject_addr.try_send(msg_from_dispatcher).unwrap();
             system.run();
         });
 
-        hopper_awaiter.await_message_count(1);
-        let recording = hopper_log_arc.lock().unwrap();
-        let record = recording.get_record::<IncipientCoresPackage>(0);
-        assert_eq!(record, &expected_pkg);
+        thread::sleep(Duration::from_millis(500));
+
+        let expected_transmit_data_msg = TransmitDataMsg {
+            endpoint: Endpoint::Socket(socket_addr),
+            last_data: true,
+            sequence_number: Some(0),
+            data: b"HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\n\r\n".to_vec(),
+            relayed_from_opt: None,
+        };
+
+        let dispatcher_recording = dispatcher_recording_arc.lock().unwrap();
+        let record = dispatcher_recording.get_record::<TransmitDataMsg>(0);
+
+        assert_eq!(&expected_transmit_data_msg, record);
     }
 
     #[test]
-    fn proxy_server_receives_http_request_from_dispatcher_then_sends_multihop_cores_package_to_hopper(
-    ) {
-        let main_cryptde = main_cryptde();
-        let alias_cryptde = alias_cryptde();
-        let consuming_wallet = make_paying_wallet(b"paying wallet");
-        let earning_wallet = make_wallet("earning wallet");
+    fn proxy_server_receives_http_request_with_no_consuming_wallet_and_sends_impersonated_response()
+    {
+        init_test_logging();
+        let cryptde = main_cryptde();
         let http_request = b"GET /index.html HTTP/1.1\r\nHost: nowhere.com\r\n\r\n";
-        let hopper_mock = Recorder::new();
-        let hopper_log_arc = hopper_mock.get_recording();
-        let hopper_awaiter = hopper_mock.get_awaiter();
-        let payload_destination_key = PublicKey::new(&[3]);
-        let route = Route::round_trip(
-            RouteSegment::new(
-                vec![
-                    &main_cryptde.public_key(),
-                    &PublicKey::new(&[1]),
-                    &PublicKey::new(&[2]),
-                    &payload_destination_key,
-                ],
-                Component::ProxyClient,
-            ),
-            RouteSegment::new(
-                vec![
-                    &payload_destination_key,
-                    &PublicKey::new(&[2]),
-                    &PublicKey::new(&[1]),
-                    &main_cryptde.public_key(),
-                ],
-                Component::ProxyServer,
-            ),
-            main_cryptde,
-            Some(consuming_wallet),
-            1234,
-            Some(contract_address(DEFAULT_CHAIN_ID)),
-        )
-        .unwrap();
-        let (neighborhood_mock, _, neighborhood_recording_arc) = make_recorder();
-        let neighborhood_mock = neighborhood_mock.route_query_response(Some(RouteQueryResponse {
-            route: route.clone(),
-            expected_services: ExpectedServices::RoundTrip(
-                vec![
-                    ExpectedService::Exit(
-                        PublicKey::new(&[3]),
-                        earning_wallet.clone(),
-                        rate_pack(101),
-                    ),
-                    ExpectedService::Nothing,
-                ],
-                vec![
-                    ExpectedService::Nothing,
-                    ExpectedService::Exit(PublicKey::new(&[3]), earning_wallet, rate_pack(102)),
-                ],
-                1234,
-            ),
-        }));
+        let (hopper, _, hopper_log_arc) = make_recorder();
+        let (neighborhood, _, neighborhood_log_arc) = make_recorder();
+        let (dispatcher, _, dispatcher_log_arc) = make_recorder();
         let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
         let stream_key = make_meaningless_stream_key();
         let expected_data = http_request.to_vec();
@@ -2017,133 +3896,286 @@ mod tests {
             is_clandestine: false,
             data: expected_data.clone(),
         };
-        let expected_http_request = PlainData::new(http_request);
-        let expected_payload = ClientRequestPayload_0v1 {
-            stream_key: stream_key.clone(),
-            sequenced_packet: SequencedPacket {
-                data: expected_http_request.into(),
-                sequence_number: 0,
-                last_data: true,
-            },
-            target_hostname: Some(String::from("nowhere.com")),
-            target_port: HTTP_PORT,
-            protocol: ProxyProtocol::HTTP,
-            originator_public_key: alias_cryptde.public_key().clone(),
-        };
-        let expected_pkg = IncipientCoresPackage::new(
-            main_cryptde,
-            route.clone(),
-            expected_payload.into(),
-            &payload_destination_key,
-        )
-        .unwrap();
-        thread::spawn(move || {
-            let stream_key_factory = StreamKeyFactoryMock::new().make_result(stream_key);
-            let system = System::new("proxy_server_receives_http_request_from_dispatcher_then_sends_cores_package_to_hopper");
-            let mut subject = ProxyServer::new(
-                main_cryptde,
-                alias_cryptde,
-                true,
-                Some(STANDARD_CONSUMING_WALLET_BALANCE),
-            );
-            subject.stream_key_factory = Box::new(stream_key_factory);
-            let subject_addr: Addr<ProxyServer> = subject.start();
-            let mut peer_actors = peer_actors_builder()
-                .hopper(hopper_mock)
-                .neighborhood(neighborhood_mock)
-                .build();
-            peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
-            subject_addr.try_send(BindMessage { peer_actors }).unwrap();
-
-            subject_addr.try_send(msg_from_dispatcher).unwrap();
+        let stream_key_factory = StreamKeyFactoryMock::new(); // can't make any stream keys; shouldn't have to
+        let system = System::new("proxy_server_receives_http_request_with_no_consuming_wallet_and_sends_impersonated_response");
+        let mut subject = ProxyServer::new(cryptde, alias_cryptde(), true, None, None);
+        subject.stream_key_factory = Box::new(stream_key_factory);
+        subject.keys_and_addrs.insert(stream_key, socket_addr);
+        let subject_addr: Addr<ProxyServer> = subject.start();
+        let mut peer_actors = peer_actors_builder()
+            .dispatcher(dispatcher)
+            .hopper(hopper)
+            .neighborhood(neighborhood)
+            .build();
+        peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
+        subject_addr.try_send(BindMessage { peer_actors }).unwrap();
 
-            system.run();
-        });
+        subject_addr.try_send(msg_from_dispatcher).unwrap();
 
-        hopper_awaiter.await_message_count(1);
-        let recording = hopper_log_arc.lock().unwrap();
-        let record = recording.get_record::<IncipientCoresPackage>(0);
-        assert_eq!(record, &expected_pkg);
-        let recording = neighborhood_recording_arc.lock().unwrap();
-        let record = recording.get_record::<RouteQueryMessage>(0);
-        assert_eq!(record, &RouteQueryMessage::data_indefinite_route_request(3));
+        System::current().stop();
+        system.run();
+        let neighborhood_recording = neighborhood_log_arc.lock().unwrap();
+        assert!(neighborhood_recording.is_empty());
+        let hopper_recording = hopper_log_arc.lock().unwrap();
+        assert!(hopper_recording.is_empty());
+        let dispatcher_recording = dispatcher_log_arc.lock().unwrap();
+        let record = dispatcher_recording.get_record::<TransmitDataMsg>(0);
+        let server_impersonator = ServerImpersonatorHttp {};
+        assert_eq!(
+            record,
+            &TransmitDataMsg {
+                endpoint: Endpoint::Socket(socket_addr),
+                last_data: true,
+                sequence_number: Some(0),
+                data: server_impersonator.consuming_wallet_absent(),
+                relayed_from_opt: None,
+            }
+        );
+        TestLogHandler::new().exists_log_containing(
+            "ERROR: ProxyServer: Browser request rejected due to missing consuming wallet",
+        );
     }
 
     #[test]
-    fn proxy_server_adds_route_for_stream_key() {
+    fn proxy_server_receives_tls_request_with_no_consuming_wallet_and_sends_impersonated_response()
+    {
+        init_test_logging();
         let cryptde = main_cryptde();
-        let http_request = b"GET /index.html HTTP/1.1\r\nHost: nowhere.com\r\n\r\n";
-        let (proxy_server_mock, proxy_server_awaiter, proxy_server_recording_arc) = make_recorder();
-        let route_query_response = Some(RouteQueryResponse {
-            route: Route { hops: vec![] },
-            expected_services: ExpectedServices::RoundTrip(vec![], vec![], 1234),
-        });
-        let (neighborhood_mock, _, _) = make_recorder();
-        let neighborhood_mock =
-            neighborhood_mock.route_query_response(route_query_response.clone());
+        let tls_request = b"Fake TLS request";
+        let (hopper, _, hopper_log_arc) = make_recorder();
+        let (neighborhood, _, neighborhood_log_arc) = make_recorder();
+        let (dispatcher, _, dispatcher_log_arc) = make_recorder();
         let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
         let stream_key = make_meaningless_stream_key();
-        let expected_data = http_request.to_vec();
+        let expected_data = tls_request.to_vec();
         let msg_from_dispatcher = InboundClientData {
             peer_addr: socket_addr.clone(),
-            reception_port: Some(HTTP_PORT),
+            reception_port: Some(TLS_PORT),
             sequence_number: Some(0),
             last_data: true,
             is_clandestine: false,
             data: expected_data.clone(),
         };
+        let stream_key_factory = StreamKeyFactoryMock::new(); // can't make any stream keys; shouldn't have to
+        let system = System::new("proxy_server_receives_tls_request_with_no_consuming_wallet_and_sends_impersonated_response");
+        let mut subject = ProxyServer::new(cryptde, alias_cryptde(), true, None, None);
+        subject.stream_key_factory = Box::new(stream_key_factory);
+        subject.keys_and_addrs.insert(stream_key, socket_addr);
+        let subject_addr: Addr<ProxyServer> = subject.start();
+        let mut peer_actors = peer_actors_builder()
+            .dispatcher(dispatcher)
+            .hopper(hopper)
+            .neighborhood(neighborhood)
+            .build();
+        peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
+        subject_addr.try_send(BindMessage { peer_actors }).unwrap();
 
-        thread::spawn(move || {
-            let stream_key_factory = StreamKeyFactoryMock::new().make_result(stream_key);
-            let system = System::new("proxy_server_adds_route_for_stream_key");
-            let mut subject = ProxyServer::new(
-                cryptde,
-                alias_cryptde(),
-                true,
-                Some(STANDARD_CONSUMING_WALLET_BALANCE),
-            );
-            subject.stream_key_factory = Box::new(stream_key_factory);
-            let subject_addr: Addr<ProxyServer> = subject.start();
-            let mut peer_actors = peer_actors_builder()
-                .proxy_server(proxy_server_mock)
-                .neighborhood(neighborhood_mock)
-                .build();
-            // Get the add_route recipient so we can partially mock it...
-            let add_route_recipient = peer_actors.proxy_server.add_route;
-            peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
-            peer_actors.proxy_server.add_route = add_route_recipient; //Partial mocking
-            subject_addr.try_send(BindMessage { peer_actors }).unwrap();
-
-            subject_addr.try_send(msg_from_dispatcher).unwrap();
-
-            system.run();
-        });
-
-        let expected_add_route_message = AddRouteMessage {
-            stream_key,
-            route: route_query_response.unwrap(),
-        };
+        subject_addr.try_send(msg_from_dispatcher).unwrap();
 
-        proxy_server_awaiter.await_message_count(1);
-        let recording = proxy_server_recording_arc.lock().unwrap();
-        let record = recording.get_record::<AddRouteMessage>(0);
-        assert_eq!(record, &expected_add_route_message);
+        System::current().stop();
+        system.run();
+        let neighborhood_recording = neighborhood_log_arc.lock().unwrap();
+        assert!(neighborhood_recording.is_empty());
+        let hopper_recording = hopper_log_arc.lock().unwrap();
+        assert!(hopper_recording.is_empty());
+        let dispatcher_recording = dispatcher_log_arc.lock().unwrap();
+        let record = dispatcher_recording.get_record::<TransmitDataMsg>(0);
+        let server_impersonator = ServerImpersonatorTls {};
+        assert_eq!(
+            record,
+            &TransmitDataMsg {
+                endpoint: Endpoint::Socket(socket_addr),
+                last_data: true,
+                sequence_number: Some(0),
+                data: server_impersonator.consuming_wallet_absent(),
+                relayed_from_opt: None,
+            }
+        );
+        TestLogHandler::new().exists_log_containing(
+            "ERROR: ProxyServer: Browser request rejected due to missing consuming wallet",
+        );
     }
 
     #[test]
-    fn proxy_server_uses_existing_route() {
+    fn proxy_server_receives_http_request_with_no_consuming_wallet_in_zero_hop_mode_and_handles_normally(
+    ) {
+        init_test_logging();
         let main_cryptde = main_cryptde();
         let alias_cryptde = alias_cryptde();
-        let http_request = b"GET /index.html HTTP/1.1\r\nHost: nowhere.com\r\n\r\n";
-        let route_query_response = Some(RouteQueryResponse {
-            route: Route { hops: vec![] },
-            expected_services: ExpectedServices::RoundTrip(
-                vec![ExpectedService::Nothing],
-                vec![],
-                1234,
-            ),
+        let expected_data = b"GET /index.html HTTP/1.1\r\nHost: nowhere.com\r\n\r\n".to_vec();
+        let expected_data_inner = expected_data.clone();
+        let expected_route = zero_hop_route_response(main_cryptde.public_key(), main_cryptde);
+        let stream_key = make_meaningless_stream_key();
+        let (hopper, hopper_awaiter, hopper_log_arc) = make_recorder();
+        let neighborhood = Recorder::new().route_query_response(Some(expected_route.clone()));
+        let neighborhood_log_arc = neighborhood.get_recording();
+        let (dispatcher, _, dispatcher_log_arc) = make_recorder();
+        thread::spawn(move || {
+            let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
+            let msg_from_dispatcher = InboundClientData {
+                peer_addr: socket_addr.clone(),
+                reception_port: Some(HTTP_PORT),
+                sequence_number: Some(0),
+                last_data: true,
+                is_clandestine: false,
+                data: expected_data_inner,
+            };
+            let stream_key_factory = StreamKeyFactoryMock::new(); // can't make any stream keys; shouldn't have to
+            let system = System::new("proxy_server_receives_http_request_with_no_consuming_wallet_in_zero_hop_mode_and_handles_normally");
+            let mut subject = ProxyServer::new(main_cryptde, alias_cryptde, false, None, None);
+            subject.stream_key_factory = Box::new(stream_key_factory);
+            subject.keys_and_addrs.insert(stream_key, socket_addr);
+            let subject_addr: Addr<ProxyServer> = subject.start();
+            let mut peer_actors = peer_actors_builder()
+                .dispatcher(dispatcher)
+                .hopper(hopper)
+                .neighborhood(neighborhood)
+                .build();
+            peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
+            subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+
+            subject_addr.try_send(msg_from_dispatcher).unwrap();
+
+            system.run();
         });
-        let (hopper_mock, hopper_awaiter, hopper_recording_arc) = make_recorder();
+        hopper_awaiter.await_message_count(1);
+        let neighborhood_recording = neighborhood_log_arc.lock().unwrap();
+        assert_eq!(
+            neighborhood_recording.get_record::<RouteQueryMessage>(0),
+            &RouteQueryMessage {
+                target_key_opt: None,
+                target_component: Component::ProxyClient,
+                minimum_hop_count: 0,
+                return_component_opt: Some(Component::ProxyServer),
+                exit_target_opt: Some(ExitTarget {
+                    hostname_opt: Some("nowhere.com".to_string()),
+                    port: 80,
+                }),
+                avoided_keys: vec![],
+            }
+        );
+        let dispatcher_recording = dispatcher_log_arc.lock().unwrap();
+        assert!(dispatcher_recording.is_empty());
+        let hopper_recording = hopper_log_arc.lock().unwrap();
+        assert_eq!(
+            hopper_recording.get_record::<IncipientCoresPackage>(0),
+            &IncipientCoresPackage::new(
+                main_cryptde,
+                expected_route.route,
+                MessageType::ClientRequest(VersionedData::new(
+                    &crate::sub_lib::migrations::client_request_payload::MIGRATIONS,
+                    &ClientRequestPayload_0v1 {
+                        stream_key,
+                        sequenced_packet: SequencedPacket::new(expected_data, 0, true),
+                        target_hostname: Some("nowhere.com".to_string()),
+                        target_port: 80,
+                        protocol: ProxyProtocol::HTTP,
+                        originator_public_key: alias_cryptde.public_key().clone(),
+                        is_resumption: false,
+                    }
+                )),
+                alias_cryptde.public_key()
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn proxy_server_receives_tls_request_with_no_consuming_wallet_in_zero_hop_mode_and_handles_normally(
+    ) {
+        init_test_logging();
+        let main_cryptde = main_cryptde();
+        let alias_cryptde = alias_cryptde();
+        let expected_data = b"Fake TLS request".to_vec();
+        let expected_data_inner = expected_data.clone();
+        let expected_route = zero_hop_route_response(main_cryptde.public_key(), main_cryptde);
+        let stream_key = make_meaningless_stream_key();
+        let (hopper, hopper_awaiter, hopper_log_arc) = make_recorder();
+        let neighborhood = Recorder::new().route_query_response(Some(expected_route.clone()));
+        let neighborhood_log_arc = neighborhood.get_recording();
+        let (dispatcher, _, dispatcher_log_arc) = make_recorder();
+        thread::spawn(move || {
+            let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
+            let msg_from_dispatcher = InboundClientData {
+                peer_addr: socket_addr.clone(),
+                reception_port: Some(TLS_PORT),
+                sequence_number: Some(0),
+                last_data: true,
+                is_clandestine: false,
+                data: expected_data_inner,
+            };
+            let stream_key_factory = StreamKeyFactoryMock::new(); // can't make any stream keys; shouldn't have to
+            let system = System::new("proxy_server_receives_tls_request_with_no_consuming_wallet_in_zero_hop_mode_and_handles_normally");
+            let mut subject = ProxyServer::new(main_cryptde, alias_cryptde, false, None, None);
+            subject.stream_key_factory = Box::new(stream_key_factory);
+            subject.keys_and_addrs.insert(stream_key, socket_addr);
+            let subject_addr: Addr<ProxyServer> = subject.start();
+            let mut peer_actors = peer_actors_builder()
+                .dispatcher(dispatcher)
+                .hopper(hopper)
+                .neighborhood(neighborhood)
+                .build();
+            peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
+            subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+
+            subject_addr.try_send(msg_from_dispatcher).unwrap();
+
+            system.run();
+        });
+        hopper_awaiter.await_message_count(1);
+        let neighborhood_recording = neighborhood_log_arc.lock().unwrap();
+        assert_eq!(
+            neighborhood_recording.get_record::<RouteQueryMessage>(0),
+            &RouteQueryMessage {
+                target_key_opt: None,
+                target_component: Component::ProxyClient,
+                minimum_hop_count: 0,
+                return_component_opt: Some(Component::ProxyServer),
+                exit_target_opt: Some(ExitTarget {
+                    hostname_opt: None,
+                    port: 443,
+                }),
+                avoided_keys: vec![],
+            }
+        );
+        let dispatcher_recording = dispatcher_log_arc.lock().unwrap();
+        assert!(dispatcher_recording.is_empty());
+        let hopper_recording = hopper_log_arc.lock().unwrap();
+        assert_eq!(
+            hopper_recording.get_record::<IncipientCoresPackage>(0),
+            &IncipientCoresPackage::new(
+                main_cryptde,
+                expected_route.route,
+                MessageType::ClientRequest(VersionedData::new(
+                    &crate::sub_lib::migrations::client_request_payload::MIGRATIONS,
+                    &ClientRequestPayload_0v1 {
+                        stream_key,
+                        sequenced_packet: SequencedPacket::new(expected_data, 0, true),
+                        target_hostname: None,
+                        target_port: 443,
+                        protocol: ProxyProtocol::TLS,
+                        originator_public_key: alias_cryptde.public_key().clone(),
+                        is_resumption: false,
+                    }
+                ),),
+                alias_cryptde.public_key()
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn proxy_server_receives_http_request_with_existing_stream_key_from_dispatcher_then_sends_cores_package_to_hopper(
+    ) {
+        let main_cryptde = main_cryptde();
+        let alias_cryptde = alias_cryptde();
+        let http_request = b"GET /index.html HTTP/1.1\r\nHost: nowhere.com\r\n\r\n";
+        let hopper_mock = Recorder::new();
+        let hopper_log_arc = hopper_mock.get_recording();
+        let hopper_awaiter = hopper_mock.get_awaiter();
+        let neighborhood_mock = Recorder::new().route_query_response(Some(
+            zero_hop_route_response(&main_cryptde.public_key(), main_cryptde),
+        ));
         let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
         let stream_key = make_meaningless_stream_key();
         let expected_data = http_request.to_vec();
@@ -2155,10 +4187,12 @@ mod tests {
             is_clandestine: false,
             data: expected_data.clone(),
         };
+        let expected_http_request = PlainData::new(http_request);
+        let route = zero_hop_route_response(main_cryptde.public_key(), main_cryptde).route;
         let expected_payload = ClientRequestPayload_0v1 {
             stream_key: stream_key.clone(),
             sequenced_packet: SequencedPacket {
-                data: PlainData::new(http_request).into(),
+                data: expected_http_request.into(),
                 sequence_number: 0,
                 last_data: true,
             },
@@ -2166,247 +4200,57 @@ mod tests {
             target_port: HTTP_PORT,
             protocol: ProxyProtocol::HTTP,
             originator_public_key: alias_cryptde.public_key().clone(),
+            is_resumption: false,
         };
         let expected_pkg = IncipientCoresPackage::new(
             main_cryptde,
-            Route { hops: vec![] },
+            route.clone(),
             expected_payload.into(),
-            &alias_cryptde.public_key().clone(),
+            alias_cryptde.public_key(),
         )
         .unwrap();
-
         thread::spawn(move || {
-            let stream_key_factory = StreamKeyFactoryMock::new().make_result(stream_key);
-            let system = System::new("proxy_server_uses_existing_route");
+            let stream_key_factory = StreamKeyFactoryMock::new(); // can't make any stream keys; shouldn't have to
+            let system = System::new("proxy_server_receives_http_request_from_dispatcher_then_sends_cores_package_to_hopper");
             let mut subject = ProxyServer::new(
                 main_cryptde,
                 alias_cryptde,
-                true,
+                false,
                 Some(STANDARD_CONSUMING_WALLET_BALANCE),
+                None,
             );
             subject.stream_key_factory = Box::new(stream_key_factory);
+            subject.keys_and_addrs.insert(stream_key, socket_addr);
             let subject_addr: Addr<ProxyServer> = subject.start();
-            let mut peer_actors = peer_actors_builder().hopper(hopper_mock).build();
+            let mut peer_actors = peer_actors_builder()
+                .hopper(hopper_mock)
+                .neighborhood(neighborhood_mock)
+                .build();
             peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
             subject_addr.try_send(BindMessage { peer_actors }).unwrap();
-            subject_addr
-                .try_send(AddRouteMessage {
-                    stream_key,
-                    route: route_query_response.unwrap(),
-                })
-                .unwrap();
+
             subject_addr.try_send(msg_from_dispatcher).unwrap();
 
             system.run();
         });
 
         hopper_awaiter.await_message_count(1);
-        let recording = hopper_recording_arc.lock().unwrap();
+        let recording = hopper_log_arc.lock().unwrap();
         let record = recording.get_record::<IncipientCoresPackage>(0);
         assert_eq!(record, &expected_pkg);
     }
 
     #[test]
-    fn proxy_server_sends_message_to_accountant_for_request_routing_service_consumed() {
-        let cryptde = main_cryptde();
-        let exit_earning_wallet = make_wallet("exit earning wallet");
-        let route_1_earning_wallet = make_wallet("route 1 earning wallet");
-        let route_2_earning_wallet = make_wallet("route 2 earning wallet");
+    fn proxy_server_applies_late_wallet_information() {
+        let main_cryptde = main_cryptde();
+        let alias_cryptde = alias_cryptde();
         let http_request = b"GET /index.html HTTP/1.1\r\nHost: nowhere.com\r\n\r\n";
-        let (accountant_mock, _, accountant_recording_arc) = make_recorder();
-        let (hopper_mock, _, hopper_recording_arc) = make_recorder();
-        let (proxy_server_mock, _, proxy_server_recording_arc) = make_recorder();
-        let route_query_response = RouteQueryResponse {
-            route: make_meaningless_route(),
-            expected_services: ExpectedServices::RoundTrip(
-                vec![
-                    ExpectedService::Nothing,
-                    ExpectedService::Routing(
-                        PublicKey::new(&[1]),
-                        route_1_earning_wallet.clone(),
-                        rate_pack(101),
-                    ),
-                    ExpectedService::Routing(
-                        PublicKey::new(&[2]),
-                        route_2_earning_wallet.clone(),
-                        rate_pack(102),
-                    ),
-                    ExpectedService::Exit(
-                        PublicKey::new(&[3]),
-                        exit_earning_wallet.clone(),
-                        rate_pack(103),
-                    ),
-                ],
-                vec![
-                    ExpectedService::Exit(
-                        PublicKey::new(&[3]),
-                        exit_earning_wallet.clone(),
-                        rate_pack(104),
-                    ),
-                    ExpectedService::Routing(
-                        PublicKey::new(&[2]),
-                        route_2_earning_wallet.clone(),
-                        rate_pack(105),
-                    ),
-                    ExpectedService::Routing(
-                        PublicKey::new(&[1]),
-                        route_1_earning_wallet.clone(),
-                        rate_pack(106),
-                    ),
-                    ExpectedService::Nothing,
-                ],
-                0,
-            ),
-        };
-        let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
-        let stream_key = make_meaningless_stream_key();
-        let expected_data = http_request.to_vec();
-        let system =
-            System::new("proxy_server_sends_message_to_accountant_for_routing_service_consumed");
-        let peer_actors = peer_actors_builder()
-            .accountant(accountant_mock)
-            .hopper(hopper_mock)
-            .proxy_server(proxy_server_mock)
-            .build();
-        let payload = ClientRequestPayload_0v1 {
-            stream_key,
-            sequenced_packet: SequencedPacket::new(expected_data, 0, false),
-            target_hostname: Some("nowhere.com".to_string()),
-            target_port: HTTP_PORT,
-            protocol: ProxyProtocol::HTTP,
-            originator_public_key: PublicKey::new(b"originator_public_key"),
-        };
-        let logger = Logger::new("test");
-
-        ProxyServer::try_transmit_to_hopper(
-            cryptde.dup(),
-            &peer_actors.hopper.from_hopper_client,
-            route_query_response,
-            payload.clone(),
-            logger,
-            socket_addr,
-            &peer_actors.dispatcher.from_dispatcher_client,
-            &peer_actors.accountant.report_exit_service_consumed,
-            &peer_actors.accountant.report_routing_service_consumed,
-            &peer_actors.proxy_server.add_return_route,
-            None,
-        )
-        .unwrap();
-
-        System::current().stop();
-        system.run();
-        let recording = hopper_recording_arc.lock().unwrap();
-        let record = recording.get_record::<IncipientCoresPackage>(0);
-        let payload_enc = &record.payload;
-        let recording = accountant_recording_arc.lock().unwrap();
-        let record = recording.get_record::<ReportRoutingServiceConsumedMessage>(1);
-        assert_eq!(
-            record,
-            &ReportRoutingServiceConsumedMessage {
-                earning_wallet: route_1_earning_wallet,
-                payload_size: payload_enc.len(),
-                service_rate: DEFAULT_RATE_PACK.routing_service_rate,
-                byte_rate: DEFAULT_RATE_PACK.routing_byte_rate,
-            }
-        );
-        let record = recording.get_record::<ReportRoutingServiceConsumedMessage>(2);
-        assert_eq!(
-            record,
-            &ReportRoutingServiceConsumedMessage {
-                earning_wallet: route_2_earning_wallet,
-                payload_size: payload_enc.len(),
-                service_rate: DEFAULT_RATE_PACK.routing_service_rate,
-                byte_rate: DEFAULT_RATE_PACK.routing_byte_rate,
-            }
-        );
-        let recording = proxy_server_recording_arc.lock().unwrap();
-        let _ = recording.get_record::<AddReturnRouteMessage>(0); // don't care about this, other than type
-        assert_eq!(recording.len(), 1); // No StreamShutdownMsg: that's the important thing
-    }
-
-    #[test]
-    fn try_transmit_to_hopper_orders_stream_shutdown_if_directed_to_do_so() {
-        let cryptde = main_cryptde();
-        let http_request = b"GET /index.html HTTP/1.1\r\nHost: nowhere.com\r\n\r\n";
-        let (proxy_server_mock, _, proxy_server_recording_arc) = make_recorder();
-        let route_query_response = RouteQueryResponse {
-            route: make_meaningless_route(),
-            expected_services: ExpectedServices::RoundTrip(
-                vec![ExpectedService::Nothing],
-                vec![ExpectedService::Nothing],
-                0,
-            ),
-        };
-        let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
-        let stream_key = make_meaningless_stream_key();
-        let expected_data = http_request.to_vec();
-        let system =
-            System::new("proxy_server_sends_message_to_accountant_for_routing_service_consumed");
-        let peer_actors = peer_actors_builder()
-            .proxy_server(proxy_server_mock)
-            .build();
-        let payload = ClientRequestPayload_0v1 {
-            stream_key,
-            sequenced_packet: SequencedPacket::new(expected_data, 0, false),
-            target_hostname: Some("nowhere.com".to_string()),
-            target_port: HTTP_PORT,
-            protocol: ProxyProtocol::HTTP,
-            originator_public_key: PublicKey::new(b"originator_public_key"),
-        };
-        let logger = Logger::new("test");
-
-        ProxyServer::try_transmit_to_hopper(
-            cryptde.dup(),
-            &peer_actors.hopper.from_hopper_client,
-            route_query_response,
-            payload.clone(),
-            logger,
-            socket_addr,
-            &peer_actors.dispatcher.from_dispatcher_client,
-            &peer_actors.accountant.report_exit_service_consumed,
-            &peer_actors.accountant.report_routing_service_consumed,
-            &peer_actors.proxy_server.add_return_route,
-            Some(&peer_actors.proxy_server.stream_shutdown_sub),
-        )
-        .unwrap();
-
-        System::current().stop();
-        system.run();
-        let recording = proxy_server_recording_arc.lock().unwrap();
-        let record = recording.get_record::<AddReturnRouteMessage>(0);
-        assert_eq!(
-            record,
-            &AddReturnRouteMessage {
-                return_route_id: 0,
-                expected_services: vec![ExpectedService::Nothing],
-                protocol: ProxyProtocol::HTTP,
-                server_name: Some("nowhere.com".to_string())
-            }
-        );
-        let record = recording.get_record::<StreamShutdownMsg>(1);
-        assert_eq!(
-            record,
-            &StreamShutdownMsg {
-                peer_addr: socket_addr,
-                stream_type: RemovedStreamType::NonClandestine(NonClandestineAttributes {
-                    reception_port: 0,
-                    sequence_number: 0,
-                }),
-                report_to_counterpart: false
-            }
-        );
-    }
-
-    #[test]
-    fn proxy_server_logs_messages_when_routing_services_are_not_requested() {
-        init_test_logging();
-        let cryptde = main_cryptde();
-        let http_request = b"GET /index.html HTTP/1.1\r\nHost: nowhere.com\r\n\r\n";
-        let (accountant_mock, _, accountant_log_arc) = make_recorder();
-        let (neighborhood_mock, _, _) = make_recorder();
-        let zero_hop_route_reponse = zero_hop_route_response(&cryptde.public_key(), cryptde);
-        let neighborhood_mock =
-            neighborhood_mock.route_query_response(Some(zero_hop_route_reponse.clone()));
+        let hopper_mock = Recorder::new();
+        let hopper_log_arc = hopper_mock.get_recording();
+        let hopper_awaiter = hopper_mock.get_awaiter();
+        let neighborhood_mock = Recorder::new().route_query_response(Some(
+            zero_hop_route_response(&main_cryptde.public_key(), main_cryptde),
+        ));
         let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
         let stream_key = make_meaningless_stream_key();
         let expected_data = http_request.to_vec();
@@ -2418,61 +4262,112 @@ mod tests {
             is_clandestine: false,
             data: expected_data.clone(),
         };
+        let expected_http_request = PlainData::new(http_request);
+        let route = zero_hop_route_response(main_cryptde.public_key(), main_cryptde).route;
+        let expected_payload = ClientRequestPayload_0v1 {
+            stream_key: stream_key.clone(),
+            sequenced_packet: SequencedPacket {
+                data: expected_http_request.into(),
+                sequence_number: 0,
+                last_data: true,
+            },
+            target_hostname: Some(String::from("nowhere.com")),
+            target_port: HTTP_PORT,
+            protocol: ProxyProtocol::HTTP,
+            originator_public_key: alias_cryptde.public_key().clone(),
+            is_resumption: false,
+        };
+        let expected_pkg = IncipientCoresPackage::new(
+            main_cryptde,
+            route.clone(),
+            expected_payload.into(),
+            alias_cryptde.public_key(),
+        )
+        .unwrap();
         thread::spawn(move || {
-            let stream_key_factory = StreamKeyFactoryMock::new().make_result(stream_key);
-            let system =
-                System::new("proxy_server_logs_messages_when_routing_services_are_not_requested");
-            let mut subject = ProxyServer::new(
-                cryptde,
-                alias_cryptde(),
-                true,
-                Some(STANDARD_CONSUMING_WALLET_BALANCE),
-            );
+            let stream_key_factory = StreamKeyFactoryMock::new(); // can't make any stream keys; shouldn't have to
+            let system = System::new("proxy_server_applies_late_wallet_information");
+            let mut subject = ProxyServer::new(main_cryptde, alias_cryptde, false, None, None);
             subject.stream_key_factory = Box::new(stream_key_factory);
+            subject.keys_and_addrs.insert(stream_key, socket_addr);
             let subject_addr: Addr<ProxyServer> = subject.start();
             let mut peer_actors = peer_actors_builder()
-                .accountant(accountant_mock)
+                .hopper(hopper_mock)
                 .neighborhood(neighborhood_mock)
                 .build();
             peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
             subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+
+            subject_addr
+                .try_send(SetConsumingWalletMessage {
+                    wallet: make_wallet("Consuming wallet"),
+                })
+                .unwrap();
+
             subject_addr.try_send(msg_from_dispatcher).unwrap();
             system.run();
         });
 
-        TestLogHandler::new()
-            .await_log_containing("DEBUG: ProxyServer: No routing services requested.", 1000);
-
-        assert_eq!(accountant_log_arc.lock().unwrap().len(), 0);
+        hopper_awaiter.await_message_count(1);
+        let recording = hopper_log_arc.lock().unwrap();
+        let record = recording.get_record::<IncipientCoresPackage>(0);
+        assert_eq!(record, &expected_pkg);
     }
 
     #[test]
-    fn proxy_server_sends_message_to_accountant_for_request_exit_service_consumed() {
-        let cryptde = main_cryptde();
+    fn proxy_server_receives_http_request_from_dispatcher_then_sends_multihop_cores_package_to_hopper(
+    ) {
+        let main_cryptde = main_cryptde();
+        let alias_cryptde = alias_cryptde();
+        let consuming_wallet = make_paying_wallet(b"paying wallet");
         let earning_wallet = make_wallet("earning wallet");
         let http_request = b"GET /index.html HTTP/1.1\r\nHost: nowhere.com\r\n\r\n";
-        let (accountant_mock, accountant_awaiter, accountant_log_arc) = make_recorder();
-        let (neighborhood_mock, _, _) = make_recorder();
+        let hopper_mock = Recorder::new();
+        let hopper_log_arc = hopper_mock.get_recording();
+        let hopper_awaiter = hopper_mock.get_awaiter();
+        let payload_destination_key = PublicKey::new(&[3]);
+        let route = Route::round_trip(
+            RouteSegment::new(
+                vec![
+                    &main_cryptde.public_key(),
+                    &PublicKey::new(&[1]),
+                    &PublicKey::new(&[2]),
+                    &payload_destination_key,
+                ],
+                Component::ProxyClient,
+            ),
+            RouteSegment::new(
+                vec![
+                    &payload_destination_key,
+                    &PublicKey::new(&[2]),
+                    &PublicKey::new(&[1]),
+                    &main_cryptde.public_key(),
+                ],
+                Component::ProxyServer,
+            ),
+            main_cryptde,
+            Some(consuming_wallet),
+            1234,
+            Some(contract_address(DEFAULT_CHAIN_ID)),
+        )
+        .unwrap();
+        let (neighborhood_mock, _, neighborhood_recording_arc) = make_recorder();
         let neighborhood_mock = neighborhood_mock.route_query_response(Some(RouteQueryResponse {
-            route: make_meaningless_route(),
+            route: route.clone(),
             expected_services: ExpectedServices::RoundTrip(
                 vec![
-                    ExpectedService::Nothing,
                     ExpectedService::Exit(
                         PublicKey::new(&[3]),
                         earning_wallet.clone(),
                         rate_pack(101),
                     ),
+                    ExpectedService::Nothing,
                 ],
                 vec![
-                    ExpectedService::Exit(
-                        PublicKey::new(&[3]),
-                        earning_wallet.clone(),
-                        rate_pack(102),
-                    ),
                     ExpectedService::Nothing,
+                    ExpectedService::Exit(PublicKey::new(&[3]), earning_wallet, rate_pack(102)),
                 ],
-                0,
+                1234,
             ),
         }));
         let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
@@ -2486,52 +4381,76 @@ mod tests {
             is_clandestine: false,
             data: expected_data.clone(),
         };
-        thread::spawn(move || {
-            let stream_key_factory = StreamKeyFactoryMock::new().make_result(stream_key);
-            let system =
-                System::new("proxy_server_sends_message_to_accountant_for_exit_service_consumed");
+        let expected_http_request = PlainData::new(http_request);
+        let expected_payload = ClientRequestPayload_0v1 {
+            stream_key: stream_key.clone(),
+            sequenced_packet: SequencedPacket {
+                data: expected_http_request.into(),
+                sequence_number: 0,
+                last_data: true,
+            },
+            target_hostname: Some(String::from("nowhere.com")),
+            target_port: HTTP_PORT,
+            protocol: ProxyProtocol::HTTP,
+            originator_public_key: alias_cryptde.public_key().clone(),
+            is_resumption: false,
+        };
+        let expected_pkg = IncipientCoresPackage::new(
+            main_cryptde,
+            route.clone(),
+            expected_payload.into(),
+            &payload_destination_key,
+        )
+        .unwrap();
+        thread::spawn(move || {
+            let stream_key_factory = StreamKeyFactoryMock::new().make_result(stream_key);
+            let system = System::new("proxy_server_receives_http_request_from_dispatcher_then_sends_cores_package_to_hopper");
             let mut subject = ProxyServer::new(
-                cryptde,
-                alias_cryptde(),
+                main_cryptde,
+                alias_cryptde,
                 true,
                 Some(STANDARD_CONSUMING_WALLET_BALANCE),
+                None,
             );
             subject.stream_key_factory = Box::new(stream_key_factory);
             let subject_addr: Addr<ProxyServer> = subject.start();
             let mut peer_actors = peer_actors_builder()
-                .accountant(accountant_mock)
+                .hopper(hopper_mock)
                 .neighborhood(neighborhood_mock)
                 .build();
             peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
             subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+
             subject_addr.try_send(msg_from_dispatcher).unwrap();
+
             system.run();
         });
 
-        accountant_awaiter.await_message_count(1);
-        let recording = accountant_log_arc.lock().unwrap();
-        let record = recording.get_record::<ReportExitServiceConsumedMessage>(0);
+        hopper_awaiter.await_message_count(1);
+        let recording = hopper_log_arc.lock().unwrap();
+        let record = recording.get_record::<IncipientCoresPackage>(0);
+        assert_eq!(record, &expected_pkg);
+        let recording = neighborhood_recording_arc.lock().unwrap();
+        let record = recording.get_record::<RouteQueryMessage>(0);
         assert_eq!(
             record,
-            &ReportExitServiceConsumedMessage {
-                earning_wallet,
-                payload_size: expected_data.len(),
-                service_rate: DEFAULT_RATE_PACK.exit_service_rate,
-                byte_rate: DEFAULT_RATE_PACK.exit_byte_rate,
-            }
+            &RouteQueryMessage::data_indefinite_route_request(3)
+                .with_exit_target(Some("nowhere.com".to_string()), 80)
         );
     }
 
     #[test]
-    fn proxy_server_logs_message_when_exit_services_are_not_requested() {
-        init_test_logging();
+    fn proxy_server_adds_route_for_stream_key() {
         let cryptde = main_cryptde();
         let http_request = b"GET /index.html HTTP/1.1\r\nHost: nowhere.com\r\n\r\n";
-        let (accountant_mock, _, accountant_log_arc) = make_recorder();
+        let (proxy_server_mock, proxy_server_awaiter, proxy_server_recording_arc) = make_recorder();
+        let route_query_response = Some(RouteQueryResponse {
+            route: Route { hops: vec![] },
+            expected_services: ExpectedServices::RoundTrip(vec![], vec![], 1234),
+        });
         let (neighborhood_mock, _, _) = make_recorder();
-        let zero_hop_route_reponse = zero_hop_route_response(&cryptde.public_key(), cryptde);
         let neighborhood_mock =
-            neighborhood_mock.route_query_response(Some(zero_hop_route_reponse.clone()));
+            neighborhood_mock.route_query_response(route_query_response.clone());
         let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
         let stream_key = make_meaningless_stream_key();
         let expected_data = http_request.to_vec();
@@ -2543,142 +4462,337 @@ mod tests {
             is_clandestine: false,
             data: expected_data.clone(),
         };
+
         thread::spawn(move || {
             let stream_key_factory = StreamKeyFactoryMock::new().make_result(stream_key);
-            let system =
-                System::new("proxy_server_logs_message_when_exit_services_are_not_consumed");
+            let system = System::new("proxy_server_adds_route_for_stream_key");
             let mut subject = ProxyServer::new(
                 cryptde,
                 alias_cryptde(),
                 true,
                 Some(STANDARD_CONSUMING_WALLET_BALANCE),
+                None,
             );
             subject.stream_key_factory = Box::new(stream_key_factory);
             let subject_addr: Addr<ProxyServer> = subject.start();
             let mut peer_actors = peer_actors_builder()
-                .accountant(accountant_mock)
+                .proxy_server(proxy_server_mock)
                 .neighborhood(neighborhood_mock)
                 .build();
+            // Get the add_route recipient so we can partially mock it...
+            let add_route_recipient = peer_actors.proxy_server.add_route;
             peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
+            peer_actors.proxy_server.add_route = add_route_recipient; //Partial mocking
             subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+
             subject_addr.try_send(msg_from_dispatcher).unwrap();
+
             system.run();
         });
 
-        TestLogHandler::new()
-            .await_log_containing("DEBUG: ProxyServer: No exit service requested.", 1000);
+        let expected_add_route_message = AddRouteMessage {
+            stream_key,
+            route: route_query_response.unwrap(),
+            is_secondary: false,
+        };
 
-        assert_eq!(accountant_log_arc.lock().unwrap().len(), 0);
+        proxy_server_awaiter.await_message_count(1);
+        let recording = proxy_server_recording_arc.lock().unwrap();
+        let record = recording.get_record::<AddRouteMessage>(0);
+        assert_eq!(record, &expected_add_route_message);
     }
 
     #[test]
-    fn proxy_server_receives_http_request_from_dispatcher_but_neighborhood_cant_make_route() {
-        init_test_logging();
-        let cryptde = main_cryptde();
+    fn proxy_server_uses_existing_route() {
+        let main_cryptde = main_cryptde();
+        let alias_cryptde = alias_cryptde();
         let http_request = b"GET /index.html HTTP/1.1\r\nHost: nowhere.com\r\n\r\n";
-        let (neighborhood_mock, _, neighborhood_recording_arc) = make_recorder();
-        let neighborhood_mock = neighborhood_mock.route_query_response(None);
-        let dispatcher = Recorder::new();
-        let dispatcher_awaiter = dispatcher.get_awaiter();
-        let dispatcher_recording_arc = dispatcher.get_recording();
+        let route_query_response = Some(RouteQueryResponse {
+            route: Route { hops: vec![] },
+            expected_services: ExpectedServices::RoundTrip(
+                vec![ExpectedService::Nothing],
+                vec![],
+                1234,
+            ),
+        });
+        let (hopper_mock, hopper_awaiter, hopper_recording_arc) = make_recorder();
         let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
+        let stream_key = make_meaningless_stream_key();
         let expected_data = http_request.to_vec();
         let msg_from_dispatcher = InboundClientData {
             peer_addr: socket_addr.clone(),
             reception_port: Some(HTTP_PORT),
             sequence_number: Some(0),
             last_data: true,
-            data: expected_data.clone(),
             is_clandestine: false,
+            data: expected_data.clone(),
+        };
+        let expected_payload = ClientRequestPayload_0v1 {
+            stream_key: stream_key.clone(),
+            sequenced_packet: SequencedPacket {
+                data: PlainData::new(http_request).into(),
+                sequence_number: 0,
+                last_data: true,
+            },
+            target_hostname: Some(String::from("nowhere.com")),
+            target_port: HTTP_PORT,
+            protocol: ProxyProtocol::HTTP,
+            originator_public_key: alias_cryptde.public_key().clone(),
+            is_resumption: false,
         };
+        let expected_pkg = IncipientCoresPackage::new(
+            main_cryptde,
+            Route { hops: vec![] },
+            expected_payload.into(),
+            &alias_cryptde.public_key().clone(),
+        )
+        .unwrap();
+
         thread::spawn(move || {
-            let system = System::new("proxy_server_receives_http_request_from_dispatcher_but_neighborhood_cant_make_route");
-            let subject = ProxyServer::new(
-                cryptde,
+            let stream_key_factory = StreamKeyFactoryMock::new().make_result(stream_key);
+            let system = System::new("proxy_server_uses_existing_route");
+            let mut subject = ProxyServer::new(
+                main_cryptde,
+                alias_cryptde,
+                true,
+                Some(STANDARD_CONSUMING_WALLET_BALANCE),
+                None,
+            );
+            subject.stream_key_factory = Box::new(stream_key_factory);
+            let subject_addr: Addr<ProxyServer> = subject.start();
+            let mut peer_actors = peer_actors_builder().hopper(hopper_mock).build();
+            peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
+            subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+            subject_addr
+                .try_send(AddRouteMessage {
+                    stream_key,
+                    route: route_query_response.unwrap(),
+                    is_secondary: false,
+                })
+                .unwrap();
+            subject_addr.try_send(msg_from_dispatcher).unwrap();
+
+            system.run();
+        });
+
+        hopper_awaiter.await_message_count(1);
+        let recording = hopper_recording_arc.lock().unwrap();
+        let record = recording.get_record::<IncipientCoresPackage>(0);
+        assert_eq!(record, &expected_pkg);
+    }
+
+    fn make_round_trip_route_query_response(relay_keys: Vec<PublicKey>) -> RouteQueryResponse {
+        let routing = |key: &PublicKey| {
+            ExpectedService::Routing(key.clone(), make_wallet("relay"), rate_pack(101))
+        };
+        let exit =
+            ExpectedService::Exit(PublicKey::new(b"exit"), make_wallet("exit"), rate_pack(103));
+        let mut over = vec![ExpectedService::Nothing];
+        over.extend(relay_keys.iter().map(routing));
+        over.push(exit.clone());
+        let mut back = vec![exit];
+        back.extend(relay_keys.iter().rev().map(routing));
+        back.push(ExpectedService::Nothing);
+        RouteQueryResponse {
+            route: Route::one_way(
+                RouteSegment::new(relay_keys.iter().collect(), Component::ProxyClient),
+                main_cryptde(),
+                None,
+                None,
+            )
+            .unwrap(),
+            expected_services: ExpectedServices::RoundTrip(over, back, 0),
+        }
+    }
+
+    #[test]
+    fn new_stream_asks_for_second_route_to_same_exit_around_its_relays_when_multipath() {
+        init_test_logging();
+        let (neighborhood_mock, neighborhood_awaiter, neighborhood_recording_arc) = make_recorder();
+        let neighborhood_mock = neighborhood_mock.route_query_response(None);
+        let stream_key = make_meaningless_stream_key();
+        let relay_keys = vec![PublicKey::new(&[1]), PublicKey::new(&[2])];
+        let route = make_round_trip_route_query_response(relay_keys.clone());
+        thread::spawn(move || {
+            let system = System::new("new_stream_asks_for_second_route_to_same_exit");
+            let mut subject = ProxyServer::new(
+                main_cryptde(),
                 alias_cryptde(),
                 true,
                 Some(STANDARD_CONSUMING_WALLET_BALANCE),
+                None,
             );
+            subject.set_multipath_mode(MultipathMode::Stripe);
             let subject_addr: Addr<ProxyServer> = subject.start();
             let mut peer_actors = peer_actors_builder()
-                .dispatcher(dispatcher)
                 .neighborhood(neighborhood_mock)
                 .build();
             peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
             subject_addr.try_send(BindMessage { peer_actors }).unwrap();
-
-            subject_addr.try_send(msg_from_dispatcher).unwrap();
+            subject_addr
+                .try_send(AddRouteMessage {
+                    stream_key,
+                    route,
+                    is_secondary: false,
+                })
+                .unwrap();
 
             system.run();
         });
 
-        dispatcher_awaiter.await_message_count(1);
-        let recording = dispatcher_recording_arc.lock().unwrap();
-        let record = recording.get_record::<TransmitDataMsg>(0);
-        let expected_msg = TransmitDataMsg {
-            endpoint: Endpoint::Socket(SocketAddr::from_str("1.2.3.4:5678").unwrap()),
-            last_data: true,
-            sequence_number: Some(0),
-            data: ServerImpersonatorHttp {}.route_query_failure_response("nowhere.com"),
-        };
-        assert_eq!(record, &expected_msg);
+        neighborhood_awaiter.await_message_count(1);
         let recording = neighborhood_recording_arc.lock().unwrap();
-        let record = recording.get_record::<RouteQueryMessage>(0);
-        assert_eq!(record, &RouteQueryMessage::data_indefinite_route_request(3));
-        TestLogHandler::new()
-            .exists_log_containing("ERROR: ProxyServer: Failed to find route to nowhere.com");
+        assert_eq!(
+            recording.get_record::<RouteQueryMessage>(0),
+            &RouteQueryMessage::data_indefinite_route_request(3)
+                .with_target_key(PublicKey::new(b"exit"))
+                .avoiding(relay_keys)
+        );
+        TestLogHandler::new().await_log_containing(
+            &format!(
+                "No second route to exit Node {} for stream {}; staying on one route",
+                PublicKey::new(b"exit"),
+                stream_key
+            ),
+            1000,
+        );
     }
 
     #[test]
-    #[should_panic(expected = "Expected RoundTrip ExpectedServices but got OneWay")]
-    fn proxy_server_panics_if_it_receives_a_one_way_route_from_a_request_for_a_round_trip_route() {
-        let _system = System::new("proxy_server_panics_if_it_receives_a_one_way_route_from_a_request_for_a_round_trip_route");
-        let peer_actors = peer_actors_builder().build();
+    fn multipath_mode_decides_which_routes_a_packet_takes() {
+        let stream_key = make_meaningless_stream_key();
+        let primary = make_round_trip_route_query_response(vec![PublicKey::new(&[1])]);
+        let secondary = make_round_trip_route_query_response(vec![PublicKey::new(&[2])]);
+        let mut subject = ProxyServer::new(
+            main_cryptde(),
+            alias_cryptde(),
+            true,
+            Some(STANDARD_CONSUMING_WALLET_BALANCE),
+            None,
+        );
+        subject
+            .stream_key_routes
+            .insert(stream_key, primary.clone());
+        let before_secondary = subject.outbound_routes(&stream_key, 1, &primary);
+        subject.add_secondary_route(stream_key, secondary.clone());
+
+        subject.set_multipath_mode(MultipathMode::Stripe);
+        let stripe_even = subject.outbound_routes(&stream_key, 2, &primary);
+        let stripe_odd = subject.outbound_routes(&stream_key, 3, &primary);
+        subject.set_multipath_mode(MultipathMode::Duplicate);
+        let duplicate = subject.outbound_routes(&stream_key, 2, &primary);
+
+        assert_eq!(before_secondary, vec![primary.clone()]);
+        assert_eq!(stripe_even, vec![primary.clone()]);
+        assert_eq!(stripe_odd, vec![secondary.clone()]);
+        assert_eq!(duplicate, vec![primary, secondary]);
+    }
+
+    #[test]
+    fn second_route_for_a_finished_stream_is_dropped() {
+        init_test_logging();
+        let stream_key = make_meaningless_stream_key();
+        let mut subject = ProxyServer::new(
+            main_cryptde(),
+            alias_cryptde(),
+            true,
+            Some(STANDARD_CONSUMING_WALLET_BALANCE),
+            None,
+        );
+        subject.set_multipath_mode(MultipathMode::Duplicate);
+
+        subject.add_secondary_route(
+            stream_key,
+            make_round_trip_route_query_response(vec![PublicKey::new(&[2])]),
+        );
+
+        assert!(subject.secondary_routes.is_empty());
+        TestLogHandler::new().exists_log_containing(&format!(
+            "DEBUG: ProxyServer: Stream {} ended before its second route arrived",
+            stream_key
+        ));
+    }
 
+    #[test]
+    fn proxy_server_sends_message_to_accountant_for_request_routing_service_consumed() {
         let cryptde = main_cryptde();
-        let route_result = RouteQueryResponse {
+        let exit_earning_wallet = make_wallet("exit earning wallet");
+        let route_1_earning_wallet = make_wallet("route 1 earning wallet");
+        let route_2_earning_wallet = make_wallet("route 2 earning wallet");
+        let http_request = b"GET /index.html HTTP/1.1\r\nHost: nowhere.com\r\n\r\n";
+        let (accountant_mock, _, accountant_recording_arc) = make_recorder();
+        let (hopper_mock, _, hopper_recording_arc) = make_recorder();
+        let (proxy_server_mock, _, proxy_server_recording_arc) = make_recorder();
+        let route_query_response = RouteQueryResponse {
             route: make_meaningless_route(),
-            expected_services: ExpectedServices::OneWay(vec![
-                ExpectedService::Nothing,
-                ExpectedService::Routing(
-                    PublicKey::new(&[1]),
-                    make_wallet("earning wallet 1"),
-                    rate_pack(101),
-                ),
-                ExpectedService::Routing(
-                    PublicKey::new(&[2]),
-                    make_wallet("earning wallet 2"),
-                    rate_pack(102),
-                ),
-                ExpectedService::Exit(
-                    PublicKey::new(&[3]),
-                    make_wallet("exit earning wallet"),
-                    rate_pack(103),
-                ),
-            ]),
+            expected_services: ExpectedServices::RoundTrip(
+                vec![
+                    ExpectedService::Nothing,
+                    ExpectedService::Routing(
+                        PublicKey::new(&[1]),
+                        route_1_earning_wallet.clone(),
+                        rate_pack(101),
+                    ),
+                    ExpectedService::Routing(
+                        PublicKey::new(&[2]),
+                        route_2_earning_wallet.clone(),
+                        rate_pack(102),
+                    ),
+                    ExpectedService::Exit(
+                        PublicKey::new(&[3]),
+                        exit_earning_wallet.clone(),
+                        rate_pack(103),
+                    ),
+                ],
+                vec![
+                    ExpectedService::Exit(
+                        PublicKey::new(&[3]),
+                        exit_earning_wallet.clone(),
+                        rate_pack(104),
+                    ),
+                    ExpectedService::Routing(
+                        PublicKey::new(&[2]),
+                        route_2_earning_wallet.clone(),
+                        rate_pack(105),
+                    ),
+                    ExpectedService::Routing(
+                        PublicKey::new(&[1]),
+                        route_1_earning_wallet.clone(),
+                        rate_pack(106),
+                    ),
+                    ExpectedService::Nothing,
+                ],
+                0,
+            ),
         };
+        let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
+        let stream_key = make_meaningless_stream_key();
+        let expected_data = http_request.to_vec();
+        let system =
+            System::new("proxy_server_sends_message_to_accountant_for_routing_service_consumed");
+        let peer_actors = peer_actors_builder()
+            .accountant(accountant_mock)
+            .hopper(hopper_mock)
+            .proxy_server(proxy_server_mock)
+            .build();
         let payload = ClientRequestPayload_0v1 {
-            stream_key: make_meaningless_stream_key(),
-            sequenced_packet: SequencedPacket {
-                data: vec![],
-                sequence_number: 0,
-                last_data: false,
-            },
-            target_hostname: None,
-            target_port: 0,
-            protocol: ProxyProtocol::TLS,
-            originator_public_key: cryptde.public_key().clone(),
+            stream_key,
+            sequenced_packet: SequencedPacket::new(expected_data, 0, false),
+            target_hostname: Some("nowhere.com".to_string()),
+            target_port: HTTP_PORT,
+            protocol: ProxyProtocol::HTTP,
+            originator_public_key: PublicKey::new(b"originator_public_key"),
+            is_resumption: false,
         };
-        let logger = Logger::new("ProxyServer");
-        let source_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
+        let logger = Logger::new("test");
 
         ProxyServer::try_transmit_to_hopper(
             cryptde.dup(),
             &peer_actors.hopper.from_hopper_client,
-            route_result,
-            payload,
+            route_query_response,
+            payload.clone(),
             logger,
-            source_addr,
+            socket_addr,
             &peer_actors.dispatcher.from_dispatcher_client,
             &peer_actors.accountant.report_exit_service_consumed,
             &peer_actors.accountant.report_routing_service_consumed,
@@ -2686,310 +4800,327 @@ mod tests {
             None,
         )
         .unwrap();
+
+        System::current().stop();
+        system.run();
+        let recording = hopper_recording_arc.lock().unwrap();
+        let record = recording.get_record::<IncipientCoresPackage>(0);
+        let payload_enc = &record.payload;
+        let recording = accountant_recording_arc.lock().unwrap();
+        let record = recording.get_record::<ReportRoutingServiceConsumedMessage>(1);
+        assert_eq!(
+            record,
+            &ReportRoutingServiceConsumedMessage {
+                earning_wallet: route_1_earning_wallet,
+                payload_size: payload_enc.len(),
+                service_rate: DEFAULT_RATE_PACK.routing_service_rate,
+                byte_rate: DEFAULT_RATE_PACK.routing_byte_rate,
+            }
+        );
+        let record = recording.get_record::<ReportRoutingServiceConsumedMessage>(2);
+        assert_eq!(
+            record,
+            &ReportRoutingServiceConsumedMessage {
+                earning_wallet: route_2_earning_wallet,
+                payload_size: payload_enc.len(),
+                service_rate: DEFAULT_RATE_PACK.routing_service_rate,
+                byte_rate: DEFAULT_RATE_PACK.routing_byte_rate,
+            }
+        );
+        let recording = proxy_server_recording_arc.lock().unwrap();
+        let _ = recording.get_record::<AddReturnRouteMessage>(0); // don't care about this, other than type
+        assert_eq!(recording.len(), 1); // No StreamShutdownMsg: that's the important thing
     }
 
     #[test]
-    fn proxy_server_receives_http_request_from_dispatcher_but_neighborhood_cant_make_route_with_no_expected_services(
-    ) {
-        init_test_logging();
+    fn try_transmit_to_hopper_orders_stream_shutdown_if_directed_to_do_so() {
         let cryptde = main_cryptde();
-        let public_key = &cryptde.public_key();
         let http_request = b"GET /index.html HTTP/1.1\r\nHost: nowhere.com\r\n\r\n";
-        let (neighborhood_mock, _, neighborhood_recording_arc) = make_recorder();
+        let (proxy_server_mock, _, proxy_server_recording_arc) = make_recorder();
         let route_query_response = RouteQueryResponse {
-            route: Route::round_trip(
-                RouteSegment::new(vec![public_key, public_key], Component::ProxyClient),
-                RouteSegment::new(vec![public_key, public_key], Component::ProxyServer),
-                cryptde,
-                None,
-                1234,
-                None,
-            )
-            .unwrap(),
-            expected_services: ExpectedServices::RoundTrip(vec![], vec![], 1234),
+            route: make_meaningless_route(),
+            expected_services: ExpectedServices::RoundTrip(
+                vec![ExpectedService::Nothing],
+                vec![ExpectedService::Nothing],
+                0,
+            ),
         };
-        let neighborhood_mock = neighborhood_mock.route_query_response(Some(route_query_response));
-        let dispatcher = Recorder::new();
-        let dispatcher_awaiter = dispatcher.get_awaiter();
-        let dispatcher_recording_arc = dispatcher.get_recording();
         let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
+        let stream_key = make_meaningless_stream_key();
+        let expected_data = http_request.to_vec();
+        let system =
+            System::new("proxy_server_sends_message_to_accountant_for_routing_service_consumed");
+        let peer_actors = peer_actors_builder()
+            .proxy_server(proxy_server_mock)
+            .build();
+        let payload = ClientRequestPayload_0v1 {
+            stream_key,
+            sequenced_packet: SequencedPacket::new(expected_data, 0, false),
+            target_hostname: Some("nowhere.com".to_string()),
+            target_port: HTTP_PORT,
+            protocol: ProxyProtocol::HTTP,
+            originator_public_key: PublicKey::new(b"originator_public_key"),
+            is_resumption: false,
+        };
+        let logger = Logger::new("test");
+
+        ProxyServer::try_transmit_to_hopper(
+            cryptde.dup(),
+            &peer_actors.hopper.from_hopper_client,
+            route_query_response,
+            payload.clone(),
+            logger,
+            socket_addr,
+            &peer_actors.dispatcher.from_dispatcher_client,
+            &peer_actors.accountant.report_exit_service_consumed,
+            &peer_actors.accountant.report_routing_service_consumed,
+            &peer_actors.proxy_server.add_return_route,
+            Some(&peer_actors.proxy_server.stream_shutdown_sub),
+        )
+        .unwrap();
+
+        System::current().stop();
+        system.run();
+        let recording = proxy_server_recording_arc.lock().unwrap();
+        let record = recording.get_record::<AddReturnRouteMessage>(0);
+        assert_eq!(
+            record,
+            &AddReturnRouteMessage {
+                return_route_id: 0,
+                expected_services: vec![ExpectedService::Nothing],
+                protocol: ProxyProtocol::HTTP,
+                server_name: Some("nowhere.com".to_string())
+            }
+        );
+        let record = recording.get_record::<StreamShutdownMsg>(1);
+        assert_eq!(
+            record,
+            &StreamShutdownMsg {
+                peer_addr: socket_addr,
+                stream_type: RemovedStreamType::NonClandestine(NonClandestineAttributes {
+                    reception_port: 0,
+                    sequence_number: 0,
+                    half_closed: false,
+                }),
+                report_to_counterpart: false
+            }
+        );
+    }
+
+    #[test]
+    fn proxy_server_logs_messages_when_routing_services_are_not_requested() {
+        init_test_logging();
+        let cryptde = main_cryptde();
+        let http_request = b"GET /index.html HTTP/1.1\r\nHost: nowhere.com\r\n\r\n";
+        let (accountant_mock, _, accountant_log_arc) = make_recorder();
+        let (neighborhood_mock, _, _) = make_recorder();
+        let zero_hop_route_reponse = zero_hop_route_response(&cryptde.public_key(), cryptde);
+        let neighborhood_mock =
+            neighborhood_mock.route_query_response(Some(zero_hop_route_reponse.clone()));
+        let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
+        let stream_key = make_meaningless_stream_key();
         let expected_data = http_request.to_vec();
         let msg_from_dispatcher = InboundClientData {
             peer_addr: socket_addr.clone(),
             reception_port: Some(HTTP_PORT),
             sequence_number: Some(0),
             last_data: true,
-            data: expected_data.clone(),
             is_clandestine: false,
+            data: expected_data.clone(),
         };
         thread::spawn(move || {
-            let system = System::new("proxy_server_receives_http_request_from_dispatcher_but_neighborhood_cant_make_route");
-            let subject = ProxyServer::new(
+            let stream_key_factory = StreamKeyFactoryMock::new().make_result(stream_key);
+            let system =
+                System::new("proxy_server_logs_messages_when_routing_services_are_not_requested");
+            let mut subject = ProxyServer::new(
                 cryptde,
                 alias_cryptde(),
                 true,
                 Some(STANDARD_CONSUMING_WALLET_BALANCE),
+                None,
             );
+            subject.stream_key_factory = Box::new(stream_key_factory);
             let subject_addr: Addr<ProxyServer> = subject.start();
             let mut peer_actors = peer_actors_builder()
-                .dispatcher(dispatcher)
+                .accountant(accountant_mock)
                 .neighborhood(neighborhood_mock)
                 .build();
             peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
             subject_addr.try_send(BindMessage { peer_actors }).unwrap();
-
             subject_addr.try_send(msg_from_dispatcher).unwrap();
-
             system.run();
         });
 
-        dispatcher_awaiter.await_message_count(1);
-        let recording = dispatcher_recording_arc.lock().unwrap();
-        let record = recording.get_record::<TransmitDataMsg>(0);
-        let expected_msg = TransmitDataMsg {
-            endpoint: Endpoint::Socket(SocketAddr::from_str("1.2.3.4:5678").unwrap()),
-            last_data: true,
-            sequence_number: Some(0),
-            data: ServerImpersonatorHttp {}.route_query_failure_response("nowhere.com"),
-        };
-        assert_eq!(record, &expected_msg);
-        let recording = neighborhood_recording_arc.lock().unwrap();
-        let record = recording.get_record::<RouteQueryMessage>(0);
-        assert_eq!(record, &RouteQueryMessage::data_indefinite_route_request(3));
         TestLogHandler::new()
-            .exists_log_containing("ERROR: ProxyServer: Failed to find route to nowhere.com");
+            .await_log_containing("DEBUG: ProxyServer: No routing services requested.", 1000);
+
+        assert_eq!(accountant_log_arc.lock().unwrap().len(), 0);
     }
 
     #[test]
-    fn proxy_server_receives_tls_client_hello_from_dispatcher_then_sends_cores_package_to_hopper() {
-        let tls_request = &[
-            0x16, // content_type: Handshake
-            0x00, 0x00, 0x00, 0x00, // version, length: don't care
-            0x01, // handshake_type: ClientHello
-            0x00, 0x00, 0x00, 0x00, 0x00, // length, version: don't care
-            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-            0x00, 0x00, // random: don't care
-            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-            0x00, 0x00, // random: don't care
-            0x00, // session_id_length
-            0x00, 0x00, // cipher_suites_length
-            0x00, // compression_methods_length
-            0x00, 0x13, // extensions_length
-            0x00, 0x00, // extension_type: server_name
-            0x00, 0x0F, // extension_length
-            0x00, 0x0D, // server_name_list_length
-            0x00, // server_name_type
-            0x00, 0x0A, // server_name_length
-            b's', b'e', b'r', b'v', b'e', b'r', b'.', b'c', b'o', b'm', // server_name
-        ];
-        let main_cryptde = main_cryptde();
-        let alias_cryptde = alias_cryptde();
-        let hopper_mock = Recorder::new();
-        let hopper_log_arc = hopper_mock.get_recording();
-        let hopper_awaiter = hopper_mock.get_awaiter();
-        let neighborhood_mock = Recorder::new().route_query_response(Some(
-            zero_hop_route_response(&main_cryptde.public_key(), main_cryptde),
-        ));
-        let stream_key = make_meaningless_stream_key();
+    fn proxy_server_sends_message_to_accountant_for_request_exit_service_consumed() {
+        let cryptde = main_cryptde();
+        let earning_wallet = make_wallet("earning wallet");
+        let http_request = b"GET /index.html HTTP/1.1\r\nHost: nowhere.com\r\n\r\n";
+        let (accountant_mock, accountant_awaiter, accountant_log_arc) = make_recorder();
+        let (neighborhood_mock, _, _) = make_recorder();
+        let neighborhood_mock = neighborhood_mock.route_query_response(Some(RouteQueryResponse {
+            route: make_meaningless_route(),
+            expected_services: ExpectedServices::RoundTrip(
+                vec![
+                    ExpectedService::Nothing,
+                    ExpectedService::Exit(
+                        PublicKey::new(&[3]),
+                        earning_wallet.clone(),
+                        rate_pack(101),
+                    ),
+                ],
+                vec![
+                    ExpectedService::Exit(
+                        PublicKey::new(&[3]),
+                        earning_wallet.clone(),
+                        rate_pack(102),
+                    ),
+                    ExpectedService::Nothing,
+                ],
+                0,
+            ),
+        }));
         let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
-        let expected_data = tls_request.to_vec();
+        let stream_key = make_meaningless_stream_key();
+        let expected_data = http_request.to_vec();
         let msg_from_dispatcher = InboundClientData {
             peer_addr: socket_addr.clone(),
-            reception_port: Some(TLS_PORT),
+            reception_port: Some(HTTP_PORT),
             sequence_number: Some(0),
-            last_data: false,
+            last_data: true,
             is_clandestine: false,
             data: expected_data.clone(),
         };
-        let expected_tls_request = PlainData::new(tls_request);
-        let route = zero_hop_route_response(main_cryptde.public_key(), main_cryptde).route;
-        let expected_payload = ClientRequestPayload_0v1 {
-            stream_key: stream_key.clone(),
-            sequenced_packet: SequencedPacket {
-                data: expected_tls_request.into(),
-                sequence_number: 0,
-                last_data: false,
-            },
-            target_hostname: Some(String::from("server.com")),
-            target_port: TLS_PORT,
-            protocol: ProxyProtocol::TLS,
-            originator_public_key: alias_cryptde.public_key().clone(),
-        };
-        let expected_pkg = IncipientCoresPackage::new(
-            main_cryptde,
-            route.clone(),
-            expected_payload.into(),
-            alias_cryptde.public_key(),
-        )
-        .unwrap();
         thread::spawn(move || {
+            let stream_key_factory = StreamKeyFactoryMock::new().make_result(stream_key);
+            let system =
+                System::new("proxy_server_sends_message_to_accountant_for_exit_service_consumed");
             let mut subject = ProxyServer::new(
-                main_cryptde,
-                alias_cryptde,
-                false,
+                cryptde,
+                alias_cryptde(),
+                true,
                 Some(STANDARD_CONSUMING_WALLET_BALANCE),
+                None,
             );
-            subject.stream_key_factory =
-                Box::new(StreamKeyFactoryMock::new().make_result(stream_key.clone()));
-            let system = System::new("proxy_server_receives_tls_client_hello_from_dispatcher_then_sends_cores_package_to_hopper");
+            subject.stream_key_factory = Box::new(stream_key_factory);
             let subject_addr: Addr<ProxyServer> = subject.start();
             let mut peer_actors = peer_actors_builder()
-                .hopper(hopper_mock)
+                .accountant(accountant_mock)
                 .neighborhood(neighborhood_mock)
                 .build();
             peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
             subject_addr.try_send(BindMessage { peer_actors }).unwrap();
-
             subject_addr.try_send(msg_from_dispatcher).unwrap();
-
             system.run();
         });
 
-        hopper_awaiter.await_message_count(1);
-        let recording = hopper_log_arc.lock().unwrap();
-        let record = recording.get_record::<IncipientCoresPackage>(0);
-        assert_eq!(record, &expected_pkg);
+        accountant_awaiter.await_message_count(1);
+        let recording = accountant_log_arc.lock().unwrap();
+        let record = recording.get_record::<ReportExitServiceConsumedMessage>(0);
+        assert_eq!(
+            record,
+            &ReportExitServiceConsumedMessage {
+                earning_wallet,
+                payload_size: expected_data.len(),
+                service_rate: DEFAULT_RATE_PACK.exit_service_rate,
+                byte_rate: DEFAULT_RATE_PACK.exit_byte_rate,
+            }
+        );
     }
 
     #[test]
-    fn proxy_server_receives_tls_handshake_packet_other_than_client_hello_from_dispatcher_then_sends_cores_package_to_hopper(
-    ) {
-        let tls_request = &[
-            0x16, // content_type: Handshake
-            0x00, 0x00, 0x00, 0x00, // version, length: don't care
-            0x10, // handshake_type: ClientKeyExchange (not important--just not ClientHello)
-            0x00, 0x00, 0x00, // length: 0
-        ];
-        let main_cryptde = main_cryptde();
-        let alias_cryptde = alias_cryptde();
-        let hopper_mock = Recorder::new();
-        let hopper_log_arc = hopper_mock.get_recording();
-        let hopper_awaiter = hopper_mock.get_awaiter();
-        let neighborhood_mock = Recorder::new().route_query_response(Some(
-            zero_hop_route_response(&main_cryptde.public_key(), main_cryptde),
-        ));
-        let stream_key = make_meaningless_stream_key();
+    fn proxy_server_logs_message_when_exit_services_are_not_requested() {
+        init_test_logging();
+        let cryptde = main_cryptde();
+        let http_request = b"GET /index.html HTTP/1.1\r\nHost: nowhere.com\r\n\r\n";
+        let (accountant_mock, _, accountant_log_arc) = make_recorder();
+        let (neighborhood_mock, _, _) = make_recorder();
+        let zero_hop_route_reponse = zero_hop_route_response(&cryptde.public_key(), cryptde);
+        let neighborhood_mock =
+            neighborhood_mock.route_query_response(Some(zero_hop_route_reponse.clone()));
         let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
-        let expected_data = tls_request.to_vec();
+        let stream_key = make_meaningless_stream_key();
+        let expected_data = http_request.to_vec();
         let msg_from_dispatcher = InboundClientData {
             peer_addr: socket_addr.clone(),
-            reception_port: Some(TLS_PORT),
+            reception_port: Some(HTTP_PORT),
             sequence_number: Some(0),
-            last_data: false,
+            last_data: true,
             is_clandestine: false,
             data: expected_data.clone(),
         };
-        let expected_tls_request = PlainData::new(tls_request);
-        let route = zero_hop_route_response(main_cryptde.public_key(), main_cryptde).route;
-        let expected_payload = ClientRequestPayload_0v1 {
-            stream_key: stream_key.clone(),
-            sequenced_packet: SequencedPacket {
-                data: expected_tls_request.into(),
-                sequence_number: 0,
-                last_data: false,
-            },
-            target_hostname: None,
-            target_port: TLS_PORT,
-            protocol: ProxyProtocol::TLS,
-            originator_public_key: alias_cryptde.public_key().clone(),
-        };
-        let expected_pkg = IncipientCoresPackage::new(
-            main_cryptde,
-            route.clone(),
-            expected_payload.into(),
-            &alias_cryptde.public_key(),
-        )
-        .unwrap();
         thread::spawn(move || {
+            let stream_key_factory = StreamKeyFactoryMock::new().make_result(stream_key);
+            let system =
+                System::new("proxy_server_logs_message_when_exit_services_are_not_consumed");
             let mut subject = ProxyServer::new(
-                main_cryptde,
-                alias_cryptde,
-                false,
+                cryptde,
+                alias_cryptde(),
+                true,
                 Some(STANDARD_CONSUMING_WALLET_BALANCE),
+                None,
             );
-            subject.stream_key_factory =
-                Box::new(StreamKeyFactoryMock::new().make_result(stream_key.clone()));
-            let system = System::new("proxy_server_receives_tls_client_hello_from_dispatcher_then_sends_cores_package_to_hopper");
+            subject.stream_key_factory = Box::new(stream_key_factory);
             let subject_addr: Addr<ProxyServer> = subject.start();
             let mut peer_actors = peer_actors_builder()
-                .hopper(hopper_mock)
+                .accountant(accountant_mock)
                 .neighborhood(neighborhood_mock)
                 .build();
             peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
             subject_addr.try_send(BindMessage { peer_actors }).unwrap();
-
             subject_addr.try_send(msg_from_dispatcher).unwrap();
-
             system.run();
         });
 
-        hopper_awaiter.await_message_count(1);
-        let recording = hopper_log_arc.lock().unwrap();
-        let record = recording.get_record::<IncipientCoresPackage>(0);
-        assert_eq!(record, &expected_pkg);
+        TestLogHandler::new()
+            .await_log_containing("DEBUG: ProxyServer: No exit service requested.", 1000);
+
+        assert_eq!(accountant_log_arc.lock().unwrap().len(), 0);
     }
 
     #[test]
-    fn proxy_server_receives_tls_packet_other_than_handshake_from_dispatcher_then_sends_cores_package_to_hopper(
-    ) {
-        let tls_request = &[
-            0xFF, // content_type: don't care, just not Handshake
-            0x00, 0x00, 0x00, 0x00, // version, length: don't care
-        ];
-        let main_cryptde = main_cryptde();
-        let alias_cryptde = alias_cryptde();
-        let hopper_mock = Recorder::new();
-        let hopper_log_arc = hopper_mock.get_recording();
-        let hopper_awaiter = hopper_mock.get_awaiter();
-        let neighborhood_mock = Recorder::new().route_query_response(Some(
-            zero_hop_route_response(&main_cryptde.public_key(), main_cryptde),
-        ));
-        let stream_key = make_meaningless_stream_key();
+    fn proxy_server_receives_http_request_from_dispatcher_but_neighborhood_cant_make_route() {
+        init_test_logging();
+        let cryptde = main_cryptde();
+        let http_request = b"GET /index.html HTTP/1.1\r\nHost: nowhere.com\r\n\r\n";
+        let (neighborhood_mock, _, neighborhood_recording_arc) = make_recorder();
+        let neighborhood_mock = neighborhood_mock.route_query_response(None);
+        let dispatcher = Recorder::new();
+        let dispatcher_awaiter = dispatcher.get_awaiter();
+        let dispatcher_recording_arc = dispatcher.get_recording();
+        let ui_gateway = Recorder::new();
+        let ui_gateway_awaiter = ui_gateway.get_awaiter();
+        let ui_gateway_recording_arc = ui_gateway.get_recording();
         let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
-        let expected_data = tls_request.to_vec();
+        let expected_data = http_request.to_vec();
         let msg_from_dispatcher = InboundClientData {
             peer_addr: socket_addr.clone(),
-            reception_port: Some(TLS_PORT),
+            reception_port: Some(HTTP_PORT),
             sequence_number: Some(0),
             last_data: true,
-            is_clandestine: false,
             data: expected_data.clone(),
+            is_clandestine: false,
         };
-        let expected_tls_request = PlainData::new(tls_request);
-        let route = zero_hop_route_response(main_cryptde.public_key(), main_cryptde).route;
-        let expected_payload = ClientRequestPayload_0v1 {
-            stream_key: stream_key.clone(),
-            sequenced_packet: SequencedPacket {
-                data: expected_tls_request.into(),
-                sequence_number: 0,
-                last_data: true,
-            },
-            target_hostname: None,
-            target_port: TLS_PORT,
-            protocol: ProxyProtocol::TLS,
-            originator_public_key: alias_cryptde.public_key().clone(),
-        };
-        let expected_pkg = IncipientCoresPackage::new(
-            main_cryptde,
-            route.clone(),
-            expected_payload.into(),
-            alias_cryptde.public_key(),
-        )
-        .unwrap();
         thread::spawn(move || {
-            let mut subject = ProxyServer::new(
-                main_cryptde,
-                alias_cryptde,
-                false,
+            let system = System::new("proxy_server_receives_http_request_from_dispatcher_but_neighborhood_cant_make_route");
+            let subject = ProxyServer::new(
+                cryptde,
+                alias_cryptde(),
+                true,
                 Some(STANDARD_CONSUMING_WALLET_BALANCE),
+                None,
             );
-            subject.stream_key_factory =
-                Box::new(StreamKeyFactoryMock::new().make_result(stream_key.clone()));
-            let system = System::new("proxy_server_receives_tls_client_hello_from_dispatcher_then_sends_cores_package_to_hopper");
             let subject_addr: Addr<ProxyServer> = subject.start();
             let mut peer_actors = peer_actors_builder()
-                .hopper(hopper_mock)
+                .dispatcher(dispatcher)
                 .neighborhood(neighborhood_mock)
+                .ui_gateway(ui_gateway)
                 .build();
             peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
             subject_addr.try_send(BindMessage { peer_actors }).unwrap();
@@ -2999,62 +5130,147 @@ mod tests {
             system.run();
         });
 
-        hopper_awaiter.await_message_count(1);
-        let recording = hopper_log_arc.lock().unwrap();
-        let record = recording.get_record::<IncipientCoresPackage>(0);
-        assert_eq!(record, &expected_pkg);
-    }
-
-    #[test]
-    fn proxy_server_receives_tls_client_hello_from_dispatcher_but_neighborhood_cant_make_route() {
-        init_test_logging();
+        dispatcher_awaiter.await_message_count(1);
+        let recording = dispatcher_recording_arc.lock().unwrap();
+        let record = recording.get_record::<TransmitDataMsg>(0);
+        let expected_msg = TransmitDataMsg {
+            endpoint: Endpoint::Socket(SocketAddr::from_str("1.2.3.4:5678").unwrap()),
+            last_data: true,
+            sequence_number: Some(0),
+            data: ServerImpersonatorHttp {}.route_query_failure_response("nowhere.com"),
+            relayed_from_opt: None,
+        };
+        assert_eq!(record, &expected_msg);
+        let recording = neighborhood_recording_arc.lock().unwrap();
+        let record = recording.get_record::<RouteQueryMessage>(0);
+        assert_eq!(
+            record,
+            &RouteQueryMessage::data_indefinite_route_request(3)
+                .with_exit_target(Some("nowhere.com".to_string()), 80)
+        );
+        TestLogHandler::new()
+            .exists_log_containing("ERROR: ProxyServer: Failed to find route to nowhere.com");
+        ui_gateway_awaiter.await_message_count(1);
+        let recording = ui_gateway_recording_arc.lock().unwrap();
+        assert_eq!(
+            recording.get_record::<NodeToUiMessage>(0),
+            &NodeToUiMessage {
+                target: AllClients,
+                body: UiRouteFailureBroadcast {
+                    hostname: "nowhere.com".to_string()
+                }
+                .tmb(0),
+            }
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Expected RoundTrip ExpectedServices but got OneWay")]
+    fn proxy_server_panics_if_it_receives_a_one_way_route_from_a_request_for_a_round_trip_route() {
+        let _system = System::new("proxy_server_panics_if_it_receives_a_one_way_route_from_a_request_for_a_round_trip_route");
+        let peer_actors = peer_actors_builder().build();
+
         let cryptde = main_cryptde();
-        let tls_request = [
-            0x16, // content_type: Handshake
-            0x00, 0x00, 0x00, 0x00, // version, length: don't care
-            0x01, // handshake_type: ClientHello
-            0x00, 0x00, 0x00, 0x00, 0x00, // length, version: don't care
-            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-            0x00, 0x00, // random: don't care
-            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-            0x00, 0x00, // random: don't care
-            0x00, // session_id_length
-            0x00, 0x00, // cipher_suites_length
-            0x00, // compression_methods_length
-            0x00, 0x13, // extensions_length
-            0x00, 0x00, // extension_type: server_name
-            0x00, 0x0F, // extension_length
-            0x00, 0x0D, // server_name_list_length
-            0x00, // server_name_type
-            0x00, 0x0A, // server_name_length
-            b's', b'e', b'r', b'v', b'e', b'r', b'.', b'c', b'o', b'm', // server_name
-        ]
-        .to_vec();
+        let route_result = RouteQueryResponse {
+            route: make_meaningless_route(),
+            expected_services: ExpectedServices::OneWay(vec![
+                ExpectedService::Nothing,
+                ExpectedService::Routing(
+                    PublicKey::new(&[1]),
+                    make_wallet("earning wallet 1"),
+                    rate_pack(101),
+                ),
+                ExpectedService::Routing(
+                    PublicKey::new(&[2]),
+                    make_wallet("earning wallet 2"),
+                    rate_pack(102),
+                ),
+                ExpectedService::Exit(
+                    PublicKey::new(&[3]),
+                    make_wallet("exit earning wallet"),
+                    rate_pack(103),
+                ),
+            ]),
+        };
+        let payload = ClientRequestPayload_0v1 {
+            stream_key: make_meaningless_stream_key(),
+            sequenced_packet: SequencedPacket {
+                data: vec![],
+                sequence_number: 0,
+                last_data: false,
+            },
+            target_hostname: None,
+            target_port: 0,
+            protocol: ProxyProtocol::TLS,
+            originator_public_key: cryptde.public_key().clone(),
+            is_resumption: false,
+        };
+        let logger = Logger::new("ProxyServer");
+        let source_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
+
+        ProxyServer::try_transmit_to_hopper(
+            cryptde.dup(),
+            &peer_actors.hopper.from_hopper_client,
+            route_result,
+            payload,
+            logger,
+            source_addr,
+            &peer_actors.dispatcher.from_dispatcher_client,
+            &peer_actors.accountant.report_exit_service_consumed,
+            &peer_actors.accountant.report_routing_service_consumed,
+            &peer_actors.proxy_server.add_return_route,
+            None,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn proxy_server_receives_http_request_from_dispatcher_but_neighborhood_cant_make_route_with_no_expected_services(
+    ) {
+        init_test_logging();
+        let cryptde = main_cryptde();
+        let public_key = &cryptde.public_key();
+        let http_request = b"GET /index.html HTTP/1.1\r\nHost: nowhere.com\r\n\r\n";
+        let (neighborhood_mock, _, neighborhood_recording_arc) = make_recorder();
+        let route_query_response = RouteQueryResponse {
+            route: Route::round_trip(
+                RouteSegment::new(vec![public_key, public_key], Component::ProxyClient),
+                RouteSegment::new(vec![public_key, public_key], Component::ProxyServer),
+                cryptde,
+                None,
+                1234,
+                None,
+            )
+            .unwrap(),
+            expected_services: ExpectedServices::RoundTrip(vec![], vec![], 1234),
+        };
+        let neighborhood_mock = neighborhood_mock.route_query_response(Some(route_query_response));
         let dispatcher = Recorder::new();
         let dispatcher_awaiter = dispatcher.get_awaiter();
         let dispatcher_recording_arc = dispatcher.get_recording();
-        let neighborhood = Recorder::new().route_query_response(None);
         let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
+        let expected_data = http_request.to_vec();
         let msg_from_dispatcher = InboundClientData {
             peer_addr: socket_addr.clone(),
-            reception_port: Some(TLS_PORT),
+            reception_port: Some(HTTP_PORT),
             sequence_number: Some(0),
             last_data: true,
-            data: tls_request,
+            data: expected_data.clone(),
             is_clandestine: false,
         };
         thread::spawn(move || {
-            let system = System::new("proxy_server_receives_tls_client_hello_from_dispatcher_but_neighborhood_cant_make_route");
+            let system = System::new("proxy_server_receives_http_request_from_dispatcher_but_neighborhood_cant_make_route");
             let subject = ProxyServer::new(
                 cryptde,
                 alias_cryptde(),
-                false,
+                true,
                 Some(STANDARD_CONSUMING_WALLET_BALANCE),
+                None,
             );
             let subject_addr: Addr<ProxyServer> = subject.start();
             let mut peer_actors = peer_actors_builder()
                 .dispatcher(dispatcher)
-                .neighborhood(neighborhood)
+                .neighborhood(neighborhood_mock)
                 .build();
             peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
             subject_addr.try_send(BindMessage { peer_actors }).unwrap();
@@ -3063,6 +5279,7 @@ mod tests {
 
             system.run();
         });
+
         dispatcher_awaiter.await_message_count(1);
         let recording = dispatcher_recording_arc.lock().unwrap();
         let record = recording.get_record::<TransmitDataMsg>(0);
@@ -3070,1362 +5287,3424 @@ mod tests {
             endpoint: Endpoint::Socket(SocketAddr::from_str("1.2.3.4:5678").unwrap()),
             last_data: true,
             sequence_number: Some(0),
-            data: ServerImpersonatorTls {}.route_query_failure_response("ignored"),
+            data: ServerImpersonatorHttp {}.route_query_failure_response("nowhere.com"),
+            relayed_from_opt: None,
         };
         assert_eq!(record, &expected_msg);
-
+        let recording = neighborhood_recording_arc.lock().unwrap();
+        let record = recording.get_record::<RouteQueryMessage>(0);
+        assert_eq!(
+            record,
+            &RouteQueryMessage::data_indefinite_route_request(3)
+                .with_exit_target(Some("nowhere.com".to_string()), 80)
+        );
         TestLogHandler::new()
-            .exists_log_containing("ERROR: ProxyServer: Failed to find route to server.com");
+            .exists_log_containing("ERROR: ProxyServer: Failed to find route to nowhere.com");
     }
 
     #[test]
-    fn proxy_server_receives_terminal_response_from_hopper() {
-        init_test_logging();
-        let system = System::new("proxy_server_receives_response_from_hopper");
-        let (dispatcher_mock, _, dispatcher_log_arc) = make_recorder();
-        let cryptde = main_cryptde();
-        let mut subject = ProxyServer::new(
-            cryptde,
-            alias_cryptde(),
-            false,
-            Some(STANDARD_CONSUMING_WALLET_BALANCE),
-        );
-        let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
+    fn proxy_server_receives_tls_client_hello_from_dispatcher_then_sends_cores_package_to_hopper() {
+        let tls_request = &[
+            0x16, // content_type: Handshake
+            0x00, 0x00, 0x00, 0x00, // version, length: don't care
+            0x01, // handshake_type: ClientHello
+            0x00, 0x00, 0x00, 0x00, 0x00, // length, version: don't care
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, // random: don't care
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, // random: don't care
+            0x00, // session_id_length
+            0x00, 0x00, // cipher_suites_length
+            0x00, // compression_methods_length
+            0x00, 0x13, // extensions_length
+            0x00, 0x00, // extension_type: server_name
+            0x00, 0x0F, // extension_length
+            0x00, 0x0D, // server_name_list_length
+            0x00, // server_name_type
+            0x00, 0x0A, // server_name_length
+            b's', b'e', b'r', b'v', b'e', b'r', b'.', b'c', b'o', b'm', // server_name
+        ];
+        let main_cryptde = main_cryptde();
+        let alias_cryptde = alias_cryptde();
+        let hopper_mock = Recorder::new();
+        let hopper_log_arc = hopper_mock.get_recording();
+        let hopper_awaiter = hopper_mock.get_awaiter();
+        let neighborhood_mock = Recorder::new().route_query_response(Some(
+            zero_hop_route_response(&main_cryptde.public_key(), main_cryptde),
+        ));
         let stream_key = make_meaningless_stream_key();
-        subject
-            .keys_and_addrs
-            .insert(stream_key.clone(), socket_addr.clone());
-        subject.route_ids_to_return_routes.insert(
-            1234,
-            AddReturnRouteMessage {
-                return_route_id: 1234,
-                expected_services: vec![ExpectedService::Nothing],
-                protocol: ProxyProtocol::TLS,
-                server_name: None,
-            },
-        );
-        let subject_addr: Addr<ProxyServer> = subject.start();
-        let remaining_route = return_route_with_id(cryptde, 1234);
-        let client_response_payload = ClientResponsePayload_0v1 {
+        let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
+        let expected_data = tls_request.to_vec();
+        let msg_from_dispatcher = InboundClientData {
+            peer_addr: socket_addr.clone(),
+            reception_port: Some(TLS_PORT),
+            sequence_number: Some(0),
+            last_data: false,
+            is_clandestine: false,
+            data: expected_data.clone(),
+        };
+        let expected_tls_request = PlainData::new(tls_request);
+        let route = zero_hop_route_response(main_cryptde.public_key(), main_cryptde).route;
+        let expected_payload = ClientRequestPayload_0v1 {
             stream_key: stream_key.clone(),
             sequenced_packet: SequencedPacket {
-                data: b"16 bytes of data".to_vec(),
-                sequence_number: 12345678,
-                last_data: true,
+                data: expected_tls_request.into(),
+                sequence_number: 0,
+                last_data: false,
             },
+            target_hostname: Some(String::from("server.com")),
+            target_port: TLS_PORT,
+            protocol: ProxyProtocol::TLS,
+            originator_public_key: alias_cryptde.public_key().clone(),
+            is_resumption: false,
         };
-        let first_expired_cores_package = ExpiredCoresPackage::new(
-            SocketAddr::from_str("1.2.3.4:1234").unwrap(),
-            Some(make_wallet("consuming")),
-            remaining_route,
-            client_response_payload,
-            0,
-        );
-        let second_expired_cores_package = first_expired_cores_package.clone();
-        let mut peer_actors = peer_actors_builder().dispatcher(dispatcher_mock).build();
-        peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
-        subject_addr.try_send(BindMessage { peer_actors }).unwrap();
-
-        subject_addr.try_send(first_expired_cores_package).unwrap();
-        subject_addr.try_send(second_expired_cores_package).unwrap(); // should generate log because stream key is now unknown
-
-        System::current().stop_with_code(0);
-        system.run();
-
-        let recording = dispatcher_log_arc.lock().unwrap();
-        let record = recording.get_record::<TransmitDataMsg>(0);
-        assert_eq!(record.endpoint, Endpoint::Socket(socket_addr));
-        assert_eq!(record.last_data, true);
-        assert_eq!(record.data, b"16 bytes of data".to_vec());
-        TestLogHandler::new().exists_log_containing(&format!("ERROR: ProxyServer: Discarding 16-byte packet 12345678 from an unrecognized stream key: {:?}", stream_key));
-    }
-
-    #[test]
-    fn handle_client_response_payload_purges_stream_keys_for_terminal_response() {
-        let cryptde = main_cryptde();
-        let mut subject = ProxyServer::new(
-            cryptde,
-            alias_cryptde(),
+        let expected_pkg = IncipientCoresPackage::new(
+            main_cryptde,
+            route.clone(),
+            expected_payload.into(),
+            alias_cryptde.public_key(),
+        )
+        .unwrap();
+        thread::spawn(move || {
+            let mut subject = ProxyServer::new(
+                main_cryptde,
+                alias_cryptde,
+                false,
+                Some(STANDARD_CONSUMING_WALLET_BALANCE),
+                None,
+            );
+            subject.stream_key_factory =
+                Box::new(StreamKeyFactoryMock::new().make_result(stream_key.clone()));
+            let system = System::new("proxy_server_receives_tls_client_hello_from_dispatcher_then_sends_cores_package_to_hopper");
+            let subject_addr: Addr<ProxyServer> = subject.start();
+            let mut peer_actors = peer_actors_builder()
+                .hopper(hopper_mock)
+                .neighborhood(neighborhood_mock)
+                .build();
+            peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
+            subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+
+            subject_addr.try_send(msg_from_dispatcher).unwrap();
+
+            system.run();
+        });
+
+        hopper_awaiter.await_message_count(1);
+        let recording = hopper_log_arc.lock().unwrap();
+        let record = recording.get_record::<IncipientCoresPackage>(0);
+        assert_eq!(record, &expected_pkg);
+    }
+
+    #[test]
+    fn proxy_server_receives_tls_handshake_packet_other_than_client_hello_from_dispatcher_then_sends_cores_package_to_hopper(
+    ) {
+        let tls_request = &[
+            0x16, // content_type: Handshake
+            0x00, 0x00, 0x00, 0x00, // version, length: don't care
+            0x10, // handshake_type: ClientKeyExchange (not important--just not ClientHello)
+            0x00, 0x00, 0x00, // length: 0
+        ];
+        let main_cryptde = main_cryptde();
+        let alias_cryptde = alias_cryptde();
+        let hopper_mock = Recorder::new();
+        let hopper_log_arc = hopper_mock.get_recording();
+        let hopper_awaiter = hopper_mock.get_awaiter();
+        let neighborhood_mock = Recorder::new().route_query_response(Some(
+            zero_hop_route_response(&main_cryptde.public_key(), main_cryptde),
+        ));
+        let stream_key = make_meaningless_stream_key();
+        let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
+        let expected_data = tls_request.to_vec();
+        let msg_from_dispatcher = InboundClientData {
+            peer_addr: socket_addr.clone(),
+            reception_port: Some(TLS_PORT),
+            sequence_number: Some(0),
+            last_data: false,
+            is_clandestine: false,
+            data: expected_data.clone(),
+        };
+        let expected_tls_request = PlainData::new(tls_request);
+        let route = zero_hop_route_response(main_cryptde.public_key(), main_cryptde).route;
+        let expected_payload = ClientRequestPayload_0v1 {
+            stream_key: stream_key.clone(),
+            sequenced_packet: SequencedPacket {
+                data: expected_tls_request.into(),
+                sequence_number: 0,
+                last_data: false,
+            },
+            target_hostname: None,
+            target_port: TLS_PORT,
+            protocol: ProxyProtocol::TLS,
+            originator_public_key: alias_cryptde.public_key().clone(),
+            is_resumption: false,
+        };
+        let expected_pkg = IncipientCoresPackage::new(
+            main_cryptde,
+            route.clone(),
+            expected_payload.into(),
+            &alias_cryptde.public_key(),
+        )
+        .unwrap();
+        thread::spawn(move || {
+            let mut subject = ProxyServer::new(
+                main_cryptde,
+                alias_cryptde,
+                false,
+                Some(STANDARD_CONSUMING_WALLET_BALANCE),
+                None,
+            );
+            subject.stream_key_factory =
+                Box::new(StreamKeyFactoryMock::new().make_result(stream_key.clone()));
+            let system = System::new("proxy_server_receives_tls_client_hello_from_dispatcher_then_sends_cores_package_to_hopper");
+            let subject_addr: Addr<ProxyServer> = subject.start();
+            let mut peer_actors = peer_actors_builder()
+                .hopper(hopper_mock)
+                .neighborhood(neighborhood_mock)
+                .build();
+            peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
+            subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+
+            subject_addr.try_send(msg_from_dispatcher).unwrap();
+
+            system.run();
+        });
+
+        hopper_awaiter.await_message_count(1);
+        let recording = hopper_log_arc.lock().unwrap();
+        let record = recording.get_record::<IncipientCoresPackage>(0);
+        assert_eq!(record, &expected_pkg);
+    }
+
+    #[test]
+    fn proxy_server_receives_tls_packet_other_than_handshake_from_dispatcher_then_sends_cores_package_to_hopper(
+    ) {
+        let tls_request = &[
+            0xFF, // content_type: don't care, just not Handshake
+            0x00, 0x00, 0x00, 0x00, // version, length: don't care
+        ];
+        let main_cryptde = main_cryptde();
+        let alias_cryptde = alias_cryptde();
+        let hopper_mock = Recorder::new();
+        let hopper_log_arc = hopper_mock.get_recording();
+        let hopper_awaiter = hopper_mock.get_awaiter();
+        let neighborhood_mock = Recorder::new().route_query_response(Some(
+            zero_hop_route_response(&main_cryptde.public_key(), main_cryptde),
+        ));
+        let stream_key = make_meaningless_stream_key();
+        let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
+        let expected_data = tls_request.to_vec();
+        let msg_from_dispatcher = InboundClientData {
+            peer_addr: socket_addr.clone(),
+            reception_port: Some(TLS_PORT),
+            sequence_number: Some(0),
+            last_data: true,
+            is_clandestine: false,
+            data: expected_data.clone(),
+        };
+        let expected_tls_request = PlainData::new(tls_request);
+        let route = zero_hop_route_response(main_cryptde.public_key(), main_cryptde).route;
+        let expected_payload = ClientRequestPayload_0v1 {
+            stream_key: stream_key.clone(),
+            sequenced_packet: SequencedPacket {
+                data: expected_tls_request.into(),
+                sequence_number: 0,
+                last_data: true,
+            },
+            target_hostname: None,
+            target_port: TLS_PORT,
+            protocol: ProxyProtocol::TLS,
+            originator_public_key: alias_cryptde.public_key().clone(),
+            is_resumption: false,
+        };
+        let expected_pkg = IncipientCoresPackage::new(
+            main_cryptde,
+            route.clone(),
+            expected_payload.into(),
+            alias_cryptde.public_key(),
+        )
+        .unwrap();
+        thread::spawn(move || {
+            let mut subject = ProxyServer::new(
+                main_cryptde,
+                alias_cryptde,
+                false,
+                Some(STANDARD_CONSUMING_WALLET_BALANCE),
+                None,
+            );
+            subject.stream_key_factory =
+                Box::new(StreamKeyFactoryMock::new().make_result(stream_key.clone()));
+            let system = System::new("proxy_server_receives_tls_client_hello_from_dispatcher_then_sends_cores_package_to_hopper");
+            let subject_addr: Addr<ProxyServer> = subject.start();
+            let mut peer_actors = peer_actors_builder()
+                .hopper(hopper_mock)
+                .neighborhood(neighborhood_mock)
+                .build();
+            peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
+            subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+
+            subject_addr.try_send(msg_from_dispatcher).unwrap();
+
+            system.run();
+        });
+
+        hopper_awaiter.await_message_count(1);
+        let recording = hopper_log_arc.lock().unwrap();
+        let record = recording.get_record::<IncipientCoresPackage>(0);
+        assert_eq!(record, &expected_pkg);
+    }
+
+    #[test]
+    fn proxy_server_receives_tls_client_hello_from_dispatcher_but_neighborhood_cant_make_route() {
+        init_test_logging();
+        let cryptde = main_cryptde();
+        let tls_request = [
+            0x16, // content_type: Handshake
+            0x00, 0x00, 0x00, 0x00, // version, length: don't care
+            0x01, // handshake_type: ClientHello
+            0x00, 0x00, 0x00, 0x00, 0x00, // length, version: don't care
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, // random: don't care
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, // random: don't care
+            0x00, // session_id_length
+            0x00, 0x00, // cipher_suites_length
+            0x00, // compression_methods_length
+            0x00, 0x13, // extensions_length
+            0x00, 0x00, // extension_type: server_name
+            0x00, 0x0F, // extension_length
+            0x00, 0x0D, // server_name_list_length
+            0x00, // server_name_type
+            0x00, 0x0A, // server_name_length
+            b's', b'e', b'r', b'v', b'e', b'r', b'.', b'c', b'o', b'm', // server_name
+        ]
+        .to_vec();
+        let dispatcher = Recorder::new();
+        let dispatcher_awaiter = dispatcher.get_awaiter();
+        let dispatcher_recording_arc = dispatcher.get_recording();
+        let neighborhood = Recorder::new().route_query_response(None);
+        let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
+        let msg_from_dispatcher = InboundClientData {
+            peer_addr: socket_addr.clone(),
+            reception_port: Some(TLS_PORT),
+            sequence_number: Some(0),
+            last_data: true,
+            data: tls_request,
+            is_clandestine: false,
+        };
+        thread::spawn(move || {
+            let system = System::new("proxy_server_receives_tls_client_hello_from_dispatcher_but_neighborhood_cant_make_route");
+            let subject = ProxyServer::new(
+                cryptde,
+                alias_cryptde(),
+                false,
+                Some(STANDARD_CONSUMING_WALLET_BALANCE),
+                None,
+            );
+            let subject_addr: Addr<ProxyServer> = subject.start();
+            let mut peer_actors = peer_actors_builder()
+                .dispatcher(dispatcher)
+                .neighborhood(neighborhood)
+                .build();
+            peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
+            subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+
+            subject_addr.try_send(msg_from_dispatcher).unwrap();
+
+            system.run();
+        });
+        dispatcher_awaiter.await_message_count(1);
+        let recording = dispatcher_recording_arc.lock().unwrap();
+        let record = recording.get_record::<TransmitDataMsg>(0);
+        let expected_msg = TransmitDataMsg {
+            endpoint: Endpoint::Socket(SocketAddr::from_str("1.2.3.4:5678").unwrap()),
+            last_data: true,
+            sequence_number: Some(0),
+            data: ServerImpersonatorTls {}.route_query_failure_response("ignored"),
+            relayed_from_opt: None,
+        };
+        assert_eq!(record, &expected_msg);
+
+        TestLogHandler::new()
+            .exists_log_containing("ERROR: ProxyServer: Failed to find route to server.com");
+    }
+
+    #[test]
+    fn proxy_server_receives_terminal_response_from_hopper() {
+        init_test_logging();
+        let system = System::new("proxy_server_receives_response_from_hopper");
+        let (dispatcher_mock, _, dispatcher_log_arc) = make_recorder();
+        let cryptde = main_cryptde();
+        let mut subject = ProxyServer::new(
+            cryptde,
+            alias_cryptde(),
+            false,
+            Some(STANDARD_CONSUMING_WALLET_BALANCE),
+            None,
+        );
+        let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
+        let stream_key = make_meaningless_stream_key();
+        subject
+            .keys_and_addrs
+            .insert(stream_key.clone(), socket_addr.clone());
+        subject.route_ids_to_return_routes.insert(
+            1234,
+            AddReturnRouteMessage {
+                return_route_id: 1234,
+                expected_services: vec![ExpectedService::Nothing],
+                protocol: ProxyProtocol::TLS,
+                server_name: None,
+            },
+        );
+        let subject_addr: Addr<ProxyServer> = subject.start();
+        let remaining_route = return_route_with_id(cryptde, 1234);
+        let client_response_payload = ClientResponsePayload_0v1 {
+            stream_key: stream_key.clone(),
+            sequenced_packet: SequencedPacket {
+                data: b"16 bytes of data".to_vec(),
+                sequence_number: 0,
+                last_data: true,
+            },
+        };
+        let first_expired_cores_package = ExpiredCoresPackage::new(
+            SocketAddr::from_str("1.2.3.4:1234").unwrap(),
+            Some(make_wallet("consuming")),
+            remaining_route,
+            client_response_payload,
+            0,
+        );
+        let second_expired_cores_package = first_expired_cores_package.clone();
+        let mut peer_actors = peer_actors_builder().dispatcher(dispatcher_mock).build();
+        peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
+        subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+
+        subject_addr.try_send(first_expired_cores_package).unwrap();
+        subject_addr.try_send(second_expired_cores_package).unwrap(); // should generate log because stream key is now unknown
+
+        System::current().stop_with_code(0);
+        system.run();
+
+        let recording = dispatcher_log_arc.lock().unwrap();
+        let record = recording.get_record::<TransmitDataMsg>(0);
+        assert_eq!(record.endpoint, Endpoint::Socket(socket_addr));
+        assert_eq!(record.last_data, true);
+        assert_eq!(record.data, b"16 bytes of data".to_vec());
+        TestLogHandler::new().exists_log_containing(&format!(
+            "ERROR: ProxyServer: Discarding 16-byte packet 0 from an unrecognized stream key: {:?}",
+            stream_key
+        ));
+    }
+
+    #[test]
+    fn handle_client_response_payload_purges_stream_keys_for_terminal_response() {
+        let cryptde = main_cryptde();
+        let mut subject = ProxyServer::new(
+            cryptde,
+            alias_cryptde(),
+            false,
+            Some(STANDARD_CONSUMING_WALLET_BALANCE),
+            None,
+        );
+        subject.subs = Some(ProxyServerOutSubs::default());
+
+        let stream_key = make_meaningless_stream_key();
+        let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
+        subject
+            .keys_and_addrs
+            .insert(stream_key.clone(), socket_addr.clone());
+        subject.stream_key_routes.insert(
+            stream_key.clone(),
+            RouteQueryResponse {
+                route: Route { hops: vec![] },
+                expected_services: ExpectedServices::RoundTrip(vec![], vec![], 1234),
+            },
+        );
+        subject.tunneled_hosts.insert(
+            stream_key.clone(),
+            Host {
+                name: "hostname".to_string(),
+                port: Some(TLS_PORT),
+            },
+        );
+        subject.route_ids_to_return_routes.insert(
+            1234,
+            AddReturnRouteMessage {
+                return_route_id: 1234,
+                expected_services: vec![],
+                protocol: ProxyProtocol::HTTP,
+                server_name: None,
+            },
+        );
+
+        let client_response_payload = ClientResponsePayload_0v1 {
+            stream_key: stream_key.clone(),
+            sequenced_packet: SequencedPacket::new(vec![], 0, true),
+        };
+
+        let (dispatcher_mock, _, _) = make_recorder();
+
+        let peer_actors = peer_actors_builder().dispatcher(dispatcher_mock).build();
+
+        subject.subs.as_mut().unwrap().dispatcher = peer_actors.dispatcher.from_dispatcher_client;
+
+        let expired_cores_package: ExpiredCoresPackage<ClientResponsePayload_0v1> =
+            ExpiredCoresPackage::new(
+                SocketAddr::from_str("1.2.3.4:1234").unwrap(),
+                Some(make_wallet("irrelevant")),
+                return_route_with_id(cryptde, 1234),
+                client_response_payload.into(),
+                0,
+            );
+
+        subject.handle_client_response_payload(&expired_cores_package);
+
+        assert!(subject.keys_and_addrs.is_empty());
+        assert!(subject.stream_key_routes.is_empty());
+        assert!(subject.tunneled_hosts.is_empty());
+    }
+
+    fn make_subject_awaiting_responses(
+        exit_cryptde: &dyn CryptDE,
+        stream_key: StreamKey,
+        socket_addr: SocketAddr,
+    ) -> (ProxyServer, Route) {
+        let cryptde = main_cryptde();
+        let mut subject = ProxyServer::new(
+            cryptde,
+            alias_cryptde(),
+            true,
+            Some(STANDARD_CONSUMING_WALLET_BALANCE),
+            None,
+        );
+        subject.keys_and_addrs.insert(stream_key, socket_addr);
+        let route = Route::round_trip(
+            RouteSegment::new(
+                vec![cryptde.public_key(), exit_cryptde.public_key()],
+                Component::ProxyClient,
+            ),
+            RouteSegment::new(
+                vec![exit_cryptde.public_key(), cryptde.public_key()],
+                Component::ProxyServer,
+            ),
+            cryptde,
+            Some(make_paying_wallet(b"consuming")),
+            1234,
+            Some(contract_address(DEFAULT_CHAIN_ID)),
+        )
+        .unwrap();
+        subject.stream_key_routes.insert(
+            stream_key,
+            RouteQueryResponse {
+                route: route.clone(),
+                expected_services: ExpectedServices::RoundTrip(
+                    vec![ExpectedService::Exit(
+                        exit_cryptde.public_key().clone(),
+                        make_wallet("exit"),
+                        DEFAULT_RATE_PACK,
+                    )],
+                    vec![],
+                    1234,
+                ),
+            },
+        );
+        subject.route_ids_to_return_routes.insert(
+            1234,
+            AddReturnRouteMessage {
+                return_route_id: 1234,
+                expected_services: vec![],
+                protocol: ProxyProtocol::HTTP,
+                server_name: None,
+            },
+        );
+        (subject, route)
+    }
+
+    fn make_response_package(
+        stream_key: StreamKey,
+        sequence_number: u64,
+        last_data: bool,
+    ) -> ExpiredCoresPackage<ClientResponsePayload_0v1> {
+        ExpiredCoresPackage::new(
+            SocketAddr::from_str("1.2.3.4:1234").unwrap(),
+            Some(make_wallet("irrelevant")),
+            return_route_with_id(main_cryptde(), 1234),
+            ClientResponsePayload_0v1 {
+                stream_key,
+                sequenced_packet: SequencedPacket::new(
+                    vec![sequence_number as u8],
+                    sequence_number,
+                    last_data,
+                ),
+            },
+            0,
+        )
+    }
+
+    fn check_stream_ack(
+        record: &IncipientCoresPackage,
+        exit_cryptde: &dyn CryptDE,
+        route: &Route,
+        stream_key: StreamKey,
+        next_sequence_number: u64,
+    ) {
+        assert_eq!(&record.route, route);
+        match decodex::<MessageType>(exit_cryptde, &record.payload).unwrap() {
+            MessageType::StreamAck(vd) => assert_eq!(
+                vd.extract(&crate::sub_lib::migrations::stream_ack_payload::MIGRATIONS)
+                    .unwrap(),
+                StreamAckPayload_0v1 {
+                    stream_key,
+                    next_sequence_number,
+                }
+            ),
+            other => panic!("Wrong payload type: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn handle_client_response_payload_keeps_stream_key_until_everything_before_last_data_arrives() {
+        let system = System::new("test");
+        let exit_cryptde = CryptDENull::from(&PublicKey::new(b"exit"), DEFAULT_CHAIN_ID);
+        let stream_key = make_meaningless_stream_key();
+        let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
+        let (mut subject, route) =
+            make_subject_awaiting_responses(&exit_cryptde, stream_key, socket_addr);
+        let (dispatcher, _, dispatcher_recording_arc) = make_recorder();
+        let (hopper, _, hopper_recording_arc) = make_recorder();
+        let peer_actors = peer_actors_builder()
+            .dispatcher(dispatcher)
+            .hopper(hopper)
+            .build();
+        let mut subs = ProxyServerOutSubs::default();
+        subs.dispatcher = peer_actors.dispatcher.from_dispatcher_client;
+        subs.hopper = peer_actors.hopper.from_hopper_client;
+        subject.subs = Some(subs);
+
+        subject.handle_client_response_payload(&make_response_package(stream_key, 1, true));
+
+        assert_eq!(
+            subject.keys_and_addrs.a_to_b(&stream_key),
+            Some(socket_addr)
+        );
+
+        subject.handle_client_response_payload(&make_response_package(stream_key, 0, false));
+
+        assert!(subject.keys_and_addrs.is_empty());
+        assert!(subject.receive_windows.is_empty());
+        System::current().stop();
+        system.run();
+        let dispatcher_recording = dispatcher_recording_arc.lock().unwrap();
+        assert_eq!(
+            dispatcher_recording
+                .get_record::<TransmitDataMsg>(0)
+                .sequence_number,
+            Some(1)
+        );
+        assert_eq!(
+            dispatcher_recording
+                .get_record::<TransmitDataMsg>(1)
+                .sequence_number,
+            Some(0)
+        );
+        assert_eq!(dispatcher_recording.len(), 2);
+        let hopper_recording = hopper_recording_arc.lock().unwrap();
+        check_stream_ack(
+            hopper_recording.get_record::<IncipientCoresPackage>(0),
+            &exit_cryptde,
+            &route,
+            stream_key,
+            0,
+        );
+        check_stream_ack(
+            hopper_recording.get_record::<IncipientCoresPackage>(1),
+            &exit_cryptde,
+            &route,
+            stream_key,
+            2,
+        );
+        assert_eq!(hopper_recording.len(), 2);
+    }
+
+    #[test]
+    fn handle_client_response_payload_acknowledges_at_intervals_and_discards_retransmissions() {
+        init_test_logging();
+        let system = System::new("test");
+        let exit_cryptde = CryptDENull::from(&PublicKey::new(b"exit"), DEFAULT_CHAIN_ID);
+        let stream_key = make_meaningless_stream_key();
+        let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
+        let (mut subject, route) =
+            make_subject_awaiting_responses(&exit_cryptde, stream_key, socket_addr);
+        let (dispatcher, _, dispatcher_recording_arc) = make_recorder();
+        let (hopper, _, hopper_recording_arc) = make_recorder();
+        let peer_actors = peer_actors_builder()
+            .dispatcher(dispatcher)
+            .hopper(hopper)
+            .build();
+        let mut subs = ProxyServerOutSubs::default();
+        subs.dispatcher = peer_actors.dispatcher.from_dispatcher_client;
+        subs.hopper = peer_actors.hopper.from_hopper_client;
+        subject.subs = Some(subs);
+
+        (0..ACK_INTERVAL).for_each(|sequence_number| {
+            subject.handle_client_response_payload(&make_response_package(
+                stream_key,
+                sequence_number,
+                false,
+            ))
+        });
+        subject.handle_client_response_payload(&make_response_package(stream_key, 3, false));
+
+        System::current().stop();
+        system.run();
+        let dispatcher_recording = dispatcher_recording_arc.lock().unwrap();
+        assert_eq!(dispatcher_recording.len(), ACK_INTERVAL as usize);
+        let hopper_recording = hopper_recording_arc.lock().unwrap();
+        check_stream_ack(
+            hopper_recording.get_record::<IncipientCoresPackage>(0),
+            &exit_cryptde,
+            &route,
+            stream_key,
+            ACK_INTERVAL,
+        );
+        check_stream_ack(
+            hopper_recording.get_record::<IncipientCoresPackage>(1),
+            &exit_cryptde,
+            &route,
+            stream_key,
+            ACK_INTERVAL,
+        );
+        assert_eq!(hopper_recording.len(), 2);
+        TestLogHandler::new().exists_log_containing(&format!(
+            "DEBUG: ProxyServer: Discarding retransmitted packet 3 of stream {}: already relayed",
+            stream_key
+        ));
+    }
+
+    #[test]
+    fn proxy_server_receives_nonterminal_response_from_hopper() {
+        let system = System::new("proxy_server_receives_response_from_hopper");
+        let (dispatcher_mock, _, dispatcher_log_arc) = make_recorder();
+        let (accountant, _, accountant_recording_arc) = make_recorder();
+        let cryptde = main_cryptde();
+        let mut subject = ProxyServer::new(
+            cryptde,
+            alias_cryptde(),
+            false,
+            Some(STANDARD_CONSUMING_WALLET_BALANCE),
+            None,
+        );
+        let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
+        let stream_key = make_meaningless_stream_key();
+        let irrelevant_public_key = PublicKey::from(&b"irrelevant"[..]);
+        subject
+            .keys_and_addrs
+            .insert(stream_key.clone(), socket_addr.clone());
+        let incoming_route_d_wallet = make_wallet("D Earning");
+        let incoming_route_e_wallet = make_wallet("E Earning");
+        let incoming_route_f_wallet = make_wallet("F Earning");
+        subject.route_ids_to_return_routes.insert(
+            1234,
+            AddReturnRouteMessage {
+                return_route_id: 1234,
+                expected_services: vec![
+                    ExpectedService::Exit(
+                        irrelevant_public_key.clone(),
+                        incoming_route_d_wallet.clone(),
+                        rate_pack(101),
+                    ),
+                    ExpectedService::Routing(
+                        irrelevant_public_key.clone(),
+                        incoming_route_e_wallet.clone(),
+                        rate_pack(102),
+                    ),
+                    ExpectedService::Routing(
+                        irrelevant_public_key.clone(),
+                        incoming_route_f_wallet.clone(),
+                        rate_pack(103),
+                    ),
+                    ExpectedService::Nothing,
+                ],
+                protocol: ProxyProtocol::TLS,
+                server_name: None,
+            },
+        );
+        let incoming_route_g_wallet = make_wallet("G Earning");
+        let incoming_route_h_wallet = make_wallet("H Earning");
+        let incoming_route_i_wallet = make_wallet("I Earning");
+        subject.route_ids_to_return_routes.insert(
+            1235,
+            AddReturnRouteMessage {
+                return_route_id: 1235,
+                expected_services: vec![
+                    ExpectedService::Exit(
+                        irrelevant_public_key.clone(),
+                        incoming_route_g_wallet.clone(),
+                        rate_pack(104),
+                    ),
+                    ExpectedService::Routing(
+                        irrelevant_public_key.clone(),
+                        incoming_route_h_wallet.clone(),
+                        rate_pack(105),
+                    ),
+                    ExpectedService::Routing(
+                        irrelevant_public_key.clone(),
+                        incoming_route_i_wallet.clone(),
+                        rate_pack(106),
+                    ),
+                    ExpectedService::Nothing,
+                ],
+                protocol: ProxyProtocol::TLS,
+                server_name: None,
+            },
+        );
+        let subject_addr: Addr<ProxyServer> = subject.start();
+        let first_client_response_payload = ClientResponsePayload_0v1 {
+            stream_key,
+            sequenced_packet: SequencedPacket {
+                data: b"some data".to_vec(),
+                sequence_number: 4321,
+                last_data: false,
+            },
+        };
+        let first_exit_size = first_client_response_payload.sequenced_packet.data.len();
+        let first_expired_cores_package: ExpiredCoresPackage<ClientResponsePayload_0v1> =
+            ExpiredCoresPackage::new(
+                SocketAddr::from_str("1.2.3.4:1234").unwrap(),
+                Some(make_wallet("irrelevant")),
+                return_route_with_id(cryptde, 1234),
+                first_client_response_payload.into(),
+                0,
+            );
+        let routing_size = first_expired_cores_package.payload_len;
+
+        let second_client_response_payload = ClientResponsePayload_0v1 {
+            stream_key,
+            sequenced_packet: SequencedPacket {
+                data: b"other data".to_vec(),
+                sequence_number: 4322,
+                last_data: false,
+            },
+        };
+        let second_exit_size = second_client_response_payload.sequenced_packet.data.len();
+        let second_expired_cores_package: ExpiredCoresPackage<ClientResponsePayload_0v1> =
+            ExpiredCoresPackage::new(
+                SocketAddr::from_str("1.2.3.5:1235").unwrap(),
+                Some(make_wallet("irrelevant")),
+                return_route_with_id(cryptde, 1235),
+                second_client_response_payload.into(),
+                0,
+            );
+        let mut peer_actors = peer_actors_builder()
+            .dispatcher(dispatcher_mock)
+            .accountant(accountant)
+            .build();
+        peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
+        subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+
+        subject_addr
+            .try_send(first_expired_cores_package.clone())
+            .unwrap();
+        subject_addr
+            .try_send(second_expired_cores_package.clone())
+            .unwrap();
+
+        System::current().stop_with_code(0);
+        system.run();
+
+        let dispatcher_recording = dispatcher_log_arc.lock().unwrap();
+        let record = dispatcher_recording.get_record::<TransmitDataMsg>(0);
+        assert_eq!(record.endpoint, Endpoint::Socket(socket_addr));
+        assert_eq!(record.last_data, false);
+        assert_eq!(record.data, b"some data".to_vec());
+        let record = dispatcher_recording.get_record::<TransmitDataMsg>(1);
+        assert_eq!(record.endpoint, Endpoint::Socket(socket_addr));
+        assert_eq!(record.last_data, false);
+        assert_eq!(record.data, b"other data".to_vec());
+        let accountant_recording = accountant_recording_arc.lock().unwrap();
+
+        check_exit_report(
+            &accountant_recording,
+            0,
+            &incoming_route_d_wallet,
+            first_exit_size,
+        );
+        check_routing_report(
+            &accountant_recording,
+            1,
+            &incoming_route_e_wallet,
+            routing_size,
+        );
+        check_routing_report(
+            &accountant_recording,
+            2,
+            &incoming_route_f_wallet,
+            routing_size,
+        );
+        let routing_size = second_expired_cores_package.payload_len;
+        check_exit_report(
+            &accountant_recording,
+            3,
+            &incoming_route_g_wallet,
+            second_exit_size,
+        );
+        check_routing_report(
+            &accountant_recording,
+            4,
+            &incoming_route_h_wallet,
+            routing_size,
+        );
+        check_routing_report(
+            &accountant_recording,
+            5,
+            &incoming_route_i_wallet,
+            routing_size,
+        );
+        assert_eq!(accountant_recording.len(), 6);
+    }
+
+    #[test]
+    fn handle_dns_resolve_failure_sends_message_to_dispatcher() {
+        let system = System::new("proxy_server_receives_response_from_routing_services");
+
+        let (dispatcher_mock, _, dispatcher_log_arc) = make_recorder();
+
+        let cryptde = main_cryptde();
+        let mut subject = ProxyServer::new(
+            cryptde,
+            alias_cryptde(),
+            false,
+            Some(STANDARD_CONSUMING_WALLET_BALANCE),
+            None,
+        );
+
+        let stream_key = make_meaningless_stream_key();
+        let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
+
+        subject
+            .keys_and_addrs
+            .insert(stream_key.clone(), socket_addr.clone());
+
+        let exit_public_key = PublicKey::from(&b"exit_key"[..]);
+        let exit_wallet = make_wallet("exit wallet");
+
+        let subject_addr: Addr<ProxyServer> = subject.start();
+
+        let dns_resolve_failure = DnsResolveFailure_0v1::new(stream_key);
+
+        let expired_cores_package: ExpiredCoresPackage<DnsResolveFailure_0v1> =
+            ExpiredCoresPackage::new(
+                SocketAddr::from_str("1.2.3.4:1234").unwrap(),
+                Some(make_wallet("irrelevant")),
+                return_route_with_id(cryptde, 1234),
+                dns_resolve_failure.into(),
+                0,
+            );
+
+        let mut peer_actors = peer_actors_builder().dispatcher(dispatcher_mock).build();
+        peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
+
+        subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+        subject_addr
+            .try_send(AddReturnRouteMessage {
+                return_route_id: 1234,
+                expected_services: vec![ExpectedService::Exit(
+                    exit_public_key.clone(),
+                    exit_wallet,
+                    rate_pack(10),
+                )],
+                protocol: ProxyProtocol::HTTP,
+                server_name: Some("server.com".to_string()),
+            })
+            .unwrap();
+
+        subject_addr.try_send(expired_cores_package).unwrap();
+
+        System::current().stop_with_code(0);
+        system.run();
+
+        let dispatcher_recording = dispatcher_log_arc.lock().unwrap();
+        let record = dispatcher_recording.get_record::<TransmitDataMsg>(0);
+        assert_eq!(
+            TransmitDataMsg {
+                endpoint: Endpoint::Socket(socket_addr),
+                last_data: true,
+                sequence_number: Some(0),
+                data: ServerImpersonatorHttp {}.dns_resolution_failure_response(
+                    &exit_public_key,
+                    Some("server.com".to_string()),
+                ),
+                relayed_from_opt: None,
+            },
+            *record
+        );
+    }
+
+    #[test]
+    fn handle_dns_resolve_failure_reports_services_consumed() {
+        let system = System::new("proxy_server_records_accounting");
+        let (accountant, _, accountant_recording_arc) = make_recorder();
+        let cryptde = main_cryptde();
+        let mut subject = ProxyServer::new(
+            cryptde,
+            alias_cryptde(),
+            false,
+            Some(STANDARD_CONSUMING_WALLET_BALANCE),
+            None,
+        );
+        let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
+        let stream_key = make_meaningless_stream_key();
+        let irrelevant_public_key = PublicKey::from(&b"irrelevant"[..]);
+        subject
+            .keys_and_addrs
+            .insert(stream_key.clone(), socket_addr.clone());
+        let incoming_route_d_wallet = make_wallet("D Earning");
+        let incoming_route_e_wallet = make_wallet("E Earning");
+        let incoming_route_f_wallet = make_wallet("F Earning");
+        subject.route_ids_to_return_routes.insert(
+            1234,
+            AddReturnRouteMessage {
+                return_route_id: 1234,
+                expected_services: vec![
+                    ExpectedService::Exit(
+                        irrelevant_public_key.clone(),
+                        incoming_route_d_wallet.clone(),
+                        rate_pack(101),
+                    ),
+                    ExpectedService::Routing(
+                        irrelevant_public_key.clone(),
+                        incoming_route_e_wallet.clone(),
+                        rate_pack(102),
+                    ),
+                    ExpectedService::Routing(
+                        irrelevant_public_key.clone(),
+                        incoming_route_f_wallet.clone(),
+                        rate_pack(103),
+                    ),
+                    ExpectedService::Nothing,
+                ],
+                protocol: ProxyProtocol::TLS,
+                server_name: Some("server.com".to_string()),
+            },
+        );
+
+        let subject_addr: Addr<ProxyServer> = subject.start();
+        let dns_resolve_failure_payload = DnsResolveFailure_0v1::new(stream_key);
+
+        let expired_cores_package: ExpiredCoresPackage<DnsResolveFailure_0v1> =
+            ExpiredCoresPackage::new(
+                SocketAddr::from_str("1.2.3.4:1234").unwrap(),
+                Some(make_wallet("irrelevant")),
+                return_route_with_id(cryptde, 1234),
+                dns_resolve_failure_payload.into(),
+                0,
+            );
+        let routing_size = expired_cores_package.payload_len;
+
+        let mut peer_actors = peer_actors_builder().accountant(accountant).build();
+        peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
+        subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+
+        subject_addr
+            .try_send(expired_cores_package.clone())
+            .unwrap();
+
+        System::current().stop_with_code(0);
+        system.run();
+
+        let accountant_recording = accountant_recording_arc.lock().unwrap();
+        check_exit_report(&accountant_recording, 0, &incoming_route_d_wallet, 0);
+        check_routing_report(
+            &accountant_recording,
+            1,
+            &incoming_route_e_wallet,
+            routing_size,
+        );
+        check_routing_report(
+            &accountant_recording,
+            2,
+            &incoming_route_f_wallet,
+            routing_size,
+        );
+        assert_eq!(accountant_recording.len(), 3);
+    }
+
+    #[test]
+    fn handle_dns_resolve_failure_sends_message_to_neighborhood() {
+        let system = System::new("test");
+
+        let (neighborhood_mock, _, neighborhood_log_arc) = make_recorder();
+
+        let cryptde = main_cryptde();
+        let mut subject = ProxyServer::new(
+            cryptde,
+            alias_cryptde(),
+            false,
+            Some(STANDARD_CONSUMING_WALLET_BALANCE),
+            None,
+        );
+
+        let stream_key = make_meaningless_stream_key();
+        let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
+
+        subject
+            .keys_and_addrs
+            .insert(stream_key.clone(), socket_addr.clone());
+
+        let exit_public_key = PublicKey::from(&b"exit_key"[..]);
+        let exit_wallet = make_wallet("exit wallet");
+        subject.route_ids_to_return_routes.insert(
+            1234,
+            AddReturnRouteMessage {
+                return_route_id: 1234,
+                expected_services: vec![ExpectedService::Exit(
+                    exit_public_key.clone(),
+                    exit_wallet,
+                    rate_pack(10),
+                )],
+                protocol: ProxyProtocol::HTTP,
+                server_name: Some("server.com".to_string()),
+            },
+        );
+
+        let subject_addr: Addr<ProxyServer> = subject.start();
+
+        let dns_resolve_failure = DnsResolveFailure_0v1::new(stream_key);
+
+        let expired_cores_package: ExpiredCoresPackage<DnsResolveFailure_0v1> =
+            ExpiredCoresPackage::new(
+                SocketAddr::from_str("1.2.3.4:1234").unwrap(),
+                Some(make_wallet("irrelevant")),
+                return_route_with_id(cryptde, 1234),
+                dns_resolve_failure.into(),
+                0,
+            );
+
+        let mut peer_actors = peer_actors_builder()
+            .neighborhood(neighborhood_mock)
+            .build();
+        peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
+
+        subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+
+        subject_addr.try_send(expired_cores_package).unwrap();
+
+        System::current().stop();
+        system.run();
+
+        let neighborhood_recording = neighborhood_log_arc.lock().unwrap();
+        let record = neighborhood_recording.get_record::<NodeRecordMetadataMessage>(0);
+        assert_eq!(
+            record,
+            &NodeRecordMetadataMessage::Desirable(exit_public_key, false)
+        );
+    }
+
+    #[test]
+    fn handle_dns_resolve_failure_logs_when_stream_key_be_gone_but_server_name_be_not() {
+        init_test_logging();
+        let system = System::new("test");
+
+        let (neighborhood_mock, _, _) = make_recorder();
+
+        let cryptde = main_cryptde();
+        let mut subject = ProxyServer::new(
+            cryptde,
+            alias_cryptde(),
+            false,
+            Some(STANDARD_CONSUMING_WALLET_BALANCE),
+            None,
+        );
+
+        let stream_key = make_meaningless_stream_key();
+        let return_route_id = 1234;
+        let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
+
+        subject
+            .keys_and_addrs
+            .insert(stream_key.clone(), socket_addr.clone());
+
+        let exit_public_key = PublicKey::from(&b"exit_key"[..]);
+        let exit_wallet = make_wallet("exit wallet");
+        subject.route_ids_to_return_routes.insert(
+            return_route_id,
+            AddReturnRouteMessage {
+                return_route_id,
+                expected_services: vec![ExpectedService::Exit(
+                    exit_public_key.clone(),
+                    exit_wallet,
+                    rate_pack(10),
+                )],
+                protocol: ProxyProtocol::HTTP,
+                server_name: Some("server.com".to_string()),
+            },
+        );
+
+        let subject_addr: Addr<ProxyServer> = subject.start();
+
+        let dns_resolve_failure = DnsResolveFailure_0v1::new(stream_key);
+
+        let expired_cores_package: ExpiredCoresPackage<DnsResolveFailure_0v1> =
+            ExpiredCoresPackage::new(
+                SocketAddr::from_str("1.2.3.4:1234").unwrap(),
+                Some(make_wallet("irrelevant")),
+                return_route_with_id(cryptde, return_route_id),
+                dns_resolve_failure.into(),
+                0,
+            );
+
+        let already_used_expired_cores_package = expired_cores_package.clone();
+
+        let mut peer_actors = peer_actors_builder()
+            .neighborhood(neighborhood_mock)
+            .build();
+        peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
+
+        subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+
+        subject_addr.try_send(expired_cores_package).unwrap();
+
+        subject_addr
+            .try_send(already_used_expired_cores_package)
+            .unwrap();
+
+        System::current().stop_with_code(0);
+        system.run();
+
+        TestLogHandler::new().exists_log_containing(
+            format!("Discarding DnsResolveFailure message for \"server.com\" from an unrecognized stream key {:?}", stream_key).as_str());
+    }
+
+    #[test]
+    fn handle_dns_resolve_failure_logs_when_stream_key_and_server_name_are_both_missing() {
+        init_test_logging();
+        let system = System::new("test");
+
+        let (neighborhood_mock, _, _) = make_recorder();
+
+        let cryptde = main_cryptde();
+        let mut subject = ProxyServer::new(
+            cryptde,
+            alias_cryptde(),
+            false,
+            Some(STANDARD_CONSUMING_WALLET_BALANCE),
+            None,
+        );
+
+        let stream_key = make_meaningless_stream_key();
+        let return_route_id = 1234;
+        let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
+
+        subject
+            .keys_and_addrs
+            .insert(stream_key.clone(), socket_addr.clone());
+
+        let exit_public_key = PublicKey::from(&b"exit_key"[..]);
+        let exit_wallet = make_wallet("exit wallet");
+        subject.route_ids_to_return_routes.insert(
+            return_route_id,
+            AddReturnRouteMessage {
+                return_route_id,
+                expected_services: vec![ExpectedService::Exit(
+                    exit_public_key.clone(),
+                    exit_wallet,
+                    rate_pack(10),
+                )],
+                protocol: ProxyProtocol::HTTP,
+                server_name: None,
+            },
+        );
+
+        let subject_addr: Addr<ProxyServer> = subject.start();
+
+        let dns_resolve_failure = DnsResolveFailure_0v1::new(stream_key);
+
+        let expired_cores_package: ExpiredCoresPackage<DnsResolveFailure_0v1> =
+            ExpiredCoresPackage::new(
+                SocketAddr::from_str("1.2.3.4:1234").unwrap(),
+                Some(make_wallet("irrelevant")),
+                return_route_with_id(cryptde, return_route_id),
+                dns_resolve_failure.into(),
+                0,
+            );
+
+        let already_used_expired_cores_package = expired_cores_package.clone();
+
+        let mut peer_actors = peer_actors_builder()
+            .neighborhood(neighborhood_mock)
+            .build();
+        peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
+
+        subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+
+        subject_addr.try_send(expired_cores_package).unwrap();
+
+        subject_addr
+            .try_send(already_used_expired_cores_package)
+            .unwrap();
+
+        System::current().stop_with_code(0);
+        system.run();
+
+        TestLogHandler::new().exists_log_containing(
+            format!("Discarding DnsResolveFailure message for <unspecified server> from an unrecognized stream key {:?}", stream_key).as_str());
+    }
+
+    #[test]
+    fn handle_dns_resolve_failure_purges_stream_keys() {
+        let cryptde = main_cryptde();
+        let (neighborhood_mock, _, _) = make_recorder();
+        let (dispatcher_mock, _, _) = make_recorder();
+
+        let mut subject = ProxyServer::new(
+            cryptde,
+            alias_cryptde(),
+            false,
+            Some(STANDARD_CONSUMING_WALLET_BALANCE),
+            None,
+        );
+        subject.subs = Some(ProxyServerOutSubs::default());
+
+        let peer_actors = peer_actors_builder()
+            .neighborhood(neighborhood_mock)
+            .dispatcher(dispatcher_mock)
+            .build();
+        subject.subs.as_mut().unwrap().update_node_record_metadata =
+            peer_actors.neighborhood.update_node_record_metadata;
+        subject.subs.as_mut().unwrap().dispatcher = peer_actors.dispatcher.from_dispatcher_client;
+
+        let stream_key = make_meaningless_stream_key();
+        let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
+        subject
+            .keys_and_addrs
+            .insert(stream_key.clone(), socket_addr.clone());
+        subject.tunneled_hosts.insert(
+            stream_key.clone(),
+            Host {
+                name: "tunneled host".to_string(),
+                port: Some(TLS_PORT),
+            },
+        );
+        subject.stream_key_routes.insert(
+            stream_key.clone(),
+            RouteQueryResponse {
+                route: Route { hops: vec![] },
+                expected_services: ExpectedServices::OneWay(vec![]),
+            },
+        );
+        subject.route_ids_to_return_routes.insert(
+            1234,
+            AddReturnRouteMessage {
+                return_route_id: 1234,
+                expected_services: vec![ExpectedService::Nothing, ExpectedService::Nothing],
+                protocol: ProxyProtocol::HTTP,
+                server_name: None,
+            },
+        );
+        let dns_resolve_failure = DnsResolveFailure_0v1::new(stream_key);
+
+        let expired_cores_package: ExpiredCoresPackage<DnsResolveFailure_0v1> =
+            ExpiredCoresPackage::new(
+                SocketAddr::from_str("1.2.3.4:1234").unwrap(),
+                Some(make_wallet("irrelevant")),
+                return_route_with_id(cryptde, 1234),
+                dns_resolve_failure.into(),
+                0,
+            );
+
+        subject.handle_dns_resolve_failure(&expired_cores_package);
+
+        assert!(subject.keys_and_addrs.is_empty());
+        assert!(subject.stream_key_routes.is_empty());
+        assert!(subject.tunneled_hosts.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Dispatcher unbound in ProxyServer")]
+    fn panics_if_dispatcher_is_unbound() {
+        let system = System::new("panics_if_dispatcher_is_unbound");
+        let cryptde = main_cryptde();
+        let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
+        let stream_key = make_meaningless_stream_key();
+        let mut subject = ProxyServer::new(
+            cryptde,
+            alias_cryptde(),
             false,
             Some(STANDARD_CONSUMING_WALLET_BALANCE),
+            None,
+        );
+        subject
+            .keys_and_addrs
+            .insert(stream_key.clone(), socket_addr.clone());
+        let remaining_route = return_route_with_id(cryptde, 4321);
+        subject.route_ids_to_return_routes.insert(
+            4321,
+            AddReturnRouteMessage {
+                return_route_id: 4321,
+                expected_services: vec![ExpectedService::Nothing],
+                protocol: ProxyProtocol::HTTP,
+                server_name: None,
+            },
+        );
+        let subject_addr: Addr<ProxyServer> = subject.start();
+
+        let client_response_payload = ClientResponsePayload_0v1 {
+            stream_key,
+            sequenced_packet: SequencedPacket {
+                data: b"data".to_vec(),
+                sequence_number: 0,
+                last_data: true,
+            },
+        };
+        let expired_cores_package = ExpiredCoresPackage::new(
+            SocketAddr::from_str("1.2.3.4:1234").unwrap(),
+            Some(make_wallet("consuming")),
+            remaining_route,
+            client_response_payload,
+            0,
+        );
+
+        subject_addr.try_send(expired_cores_package).unwrap();
+
+        System::current().stop_with_code(0);
+        system.run();
+    }
+
+    #[test]
+    #[should_panic(expected = "Neighborhood unbound in ProxyServer")]
+    fn panics_if_hopper_is_unbound() {
+        let system = System::new("panics_if_hopper_is_unbound");
+        let http_request = b"GET /index.html HTTP/1.1\r\nHost: nowhere.com\r\n\r\n";
+        let subject = ProxyServer::new(main_cryptde(), alias_cryptde(), false, None, None);
+        let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
+        let expected_data = http_request.to_vec();
+        let msg_from_dispatcher = InboundClientData {
+            peer_addr: socket_addr.clone(),
+            reception_port: Some(53),
+            sequence_number: Some(0),
+            last_data: false,
+            is_clandestine: false,
+            data: expected_data.clone(),
+        };
+        let subject_addr: Addr<ProxyServer> = subject.start();
+
+        subject_addr.try_send(msg_from_dispatcher).unwrap();
+
+        System::current().stop_with_code(0);
+        system.run();
+    }
+
+    #[test]
+    fn report_response_services_consumed_complains_and_drops_package_if_return_route_id_is_unrecognized(
+    ) {
+        init_test_logging();
+        let cryptde = main_cryptde();
+        let (dispatcher, _, dispatcher_recording_arc) = make_recorder();
+        let (accountant, _, accountant_recording_arc) = make_recorder();
+        let system = System::new("report_response_services_consumed_complains_and_drops_package_if_return_route_id_is_unrecognized");
+        let mut subject = ProxyServer::new(
+            cryptde,
+            alias_cryptde(),
+            true,
+            Some(STANDARD_CONSUMING_WALLET_BALANCE),
+            None,
+        );
+        let stream_key = make_meaningless_stream_key();
+        subject
+            .keys_and_addrs
+            .insert(stream_key, SocketAddr::from_str("1.2.3.4:5678").unwrap());
+        let subject_addr: Addr<ProxyServer> = subject.start();
+        let mut peer_actors = peer_actors_builder()
+            .dispatcher(dispatcher)
+            .accountant(accountant)
+            .build();
+        peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
+        let client_response_payload = ClientResponsePayload_0v1 {
+            stream_key,
+            sequenced_packet: SequencedPacket {
+                data: b"some data".to_vec(),
+                sequence_number: 4321,
+                last_data: false,
+            },
+        };
+        let expired_cores_package = ExpiredCoresPackage::new(
+            SocketAddr::from_str("1.2.3.4:1234").unwrap(),
+            Some(make_wallet("irrelevant")),
+            return_route_with_id(cryptde, 1234),
+            client_response_payload,
+            0,
+        );
+        subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+
+        subject_addr.try_send(expired_cores_package).unwrap();
+
+        System::current().stop_with_code(0);
+        system.run();
+        TestLogHandler::new().exists_log_containing("ERROR: ProxyServer: Can't report services consumed: received response with bogus return-route ID 1234. Ignoring");
+        assert_eq!(dispatcher_recording_arc.lock().unwrap().len(), 0);
+        assert_eq!(accountant_recording_arc.lock().unwrap().len(), 0);
+    }
+
+    #[test]
+    fn report_response_services_consumed_complains_and_drops_package_if_return_route_id_is_unreadable(
+    ) {
+        init_test_logging();
+        let cryptde = main_cryptde();
+        let (dispatcher, _, dispatcher_recording_arc) = make_recorder();
+        let (accountant, _, accountant_recording_arc) = make_recorder();
+        let system = System::new("report_response_services_consumed_complains_and_drops_package_if_return_route_id_is_unreadable");
+        let mut subject = ProxyServer::new(
+            cryptde,
+            alias_cryptde(),
+            true,
+            Some(STANDARD_CONSUMING_WALLET_BALANCE),
+            None,
         );
-        subject.subs = Some(ProxyServerOutSubs::default());
+        let stream_key = make_meaningless_stream_key();
+        subject
+            .keys_and_addrs
+            .insert(stream_key, SocketAddr::from_str("1.2.3.4:5678").unwrap());
+        let subject_addr: Addr<ProxyServer> = subject.start();
+        let mut peer_actors = peer_actors_builder()
+            .dispatcher(dispatcher)
+            .accountant(accountant)
+            .build();
+        peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
+        let client_response_payload = ClientResponsePayload_0v1 {
+            stream_key,
+            sequenced_packet: SequencedPacket {
+                data: b"some data".to_vec(),
+                sequence_number: 4321,
+                last_data: false,
+            },
+        };
+        let expired_cores_package = ExpiredCoresPackage::new(
+            SocketAddr::from_str("1.2.3.4:1234").unwrap(),
+            Some(make_wallet("irrelevant")),
+            Route {
+                hops: vec![make_cover_hop(cryptde), CryptData::new(&[0])],
+            },
+            client_response_payload,
+            0,
+        );
+        subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+
+        subject_addr.try_send(expired_cores_package).unwrap();
+
+        System::current().stop_with_code(0);
+        system.run();
+        TestLogHandler::new().exists_log_containing(
+            "ERROR: ProxyServer: Can't report services consumed: DecryptionError(InvalidKey(\"Could not decrypt with",
+        );
+        assert_eq!(dispatcher_recording_arc.lock().unwrap().len(), 0);
+        assert_eq!(accountant_recording_arc.lock().unwrap().len(), 0);
+    }
 
+    #[test]
+    fn return_route_ids_expire_when_instructed() {
+        init_test_logging();
+        let cryptde = main_cryptde();
         let stream_key = make_meaningless_stream_key();
-        let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let system = System::new("report_response_services_consumed_complains_and_drops_package_if_return_route_id_does_not_exist");
+            let mut subject = ProxyServer::new(
+                cryptde,
+                alias_cryptde(),
+                true,
+                Some(STANDARD_CONSUMING_WALLET_BALANCE),
+                None,
+            );
+            subject.route_ids_to_return_routes = TtlHashMap::new(Duration::from_millis(250));
+            subject
+                .keys_and_addrs
+                .insert(stream_key, SocketAddr::from_str("1.2.3.4:5678").unwrap());
+            subject.route_ids_to_return_routes.insert(
+                1234,
+                AddReturnRouteMessage {
+                    return_route_id: 1234,
+                    expected_services: vec![],
+                    protocol: ProxyProtocol::TLS,
+                    server_name: None,
+                },
+            );
+            let subject_addr: Addr<ProxyServer> = subject.start();
+            let mut peer_actors = peer_actors_builder().build();
+            peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
+            subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+            tx.send(subject_addr).unwrap();
+
+            system.run();
+        });
+
+        let subject_addr = rx.recv().unwrap();
+
+        thread::sleep(Duration::from_millis(300));
+
+        let client_response_payload = ClientResponsePayload_0v1 {
+            stream_key,
+            sequenced_packet: SequencedPacket {
+                data: b"some data".to_vec(),
+                sequence_number: 4321,
+                last_data: false,
+            },
+        };
+        let expired_cores_package = ExpiredCoresPackage::new(
+            SocketAddr::from_str("1.2.3.4:1234").unwrap(),
+            Some(make_wallet("irrelevant")),
+            return_route_with_id(cryptde, 1234),
+            client_response_payload,
+            0,
+        );
+        subject_addr.try_send(expired_cores_package).unwrap();
+
+        TestLogHandler::new().await_log_containing("ERROR: ProxyServer: Can't report services consumed: received response with bogus return-route ID 1234. Ignoring", 1000);
+    }
+
+    #[test]
+    fn handle_stream_shutdown_msg_handles_unknown_peer_addr() {
+        let mut subject = ProxyServer::new(main_cryptde(), alias_cryptde(), true, None, None);
+        let unaffected_socket_addr = SocketAddr::from_str("2.3.4.5:6789").unwrap();
+        let unaffected_stream_key =
+            StreamKey::new(main_cryptde().public_key().clone(), unaffected_socket_addr);
         subject
             .keys_and_addrs
-            .insert(stream_key.clone(), socket_addr.clone());
+            .insert(unaffected_stream_key, unaffected_socket_addr);
         subject.stream_key_routes.insert(
-            stream_key.clone(),
+            unaffected_stream_key,
             RouteQueryResponse {
                 route: Route { hops: vec![] },
                 expected_services: ExpectedServices::RoundTrip(vec![], vec![], 1234),
             },
         );
+        subject.tunneled_hosts.insert(
+            unaffected_stream_key,
+            Host {
+                name: "blah".to_string(),
+                port: Some(TLS_PORT),
+            },
+        );
+
+        subject.handle_stream_shutdown_msg(StreamShutdownMsg {
+            peer_addr: SocketAddr::from_str("1.2.3.4:5678").unwrap(),
+            stream_type: RemovedStreamType::NonClandestine(NonClandestineAttributes {
+                reception_port: HTTP_PORT,
+                sequence_number: 1234,
+                half_closed: false,
+            }),
+            report_to_counterpart: true,
+        });
+
+        // Subject is unbound but didn't panic; therefore, no attempt to send to Hopper: perfect!
+        assert!(subject
+            .keys_and_addrs
+            .a_to_b(&unaffected_stream_key)
+            .is_some());
+        assert!(subject
+            .stream_key_routes
+            .contains_key(&unaffected_stream_key));
+        assert!(subject.tunneled_hosts.contains_key(&unaffected_stream_key));
+    }
+
+    #[test]
+    fn handle_stream_shutdown_msg_reports_to_counterpart_through_tunnel_when_necessary() {
+        let system = System::new("test");
+        let mut subject = ProxyServer::new(
+            main_cryptde(),
+            alias_cryptde(),
+            true,
+            Some(STANDARD_CONSUMING_WALLET_BALANCE),
+            None,
+        );
+        let unaffected_socket_addr = SocketAddr::from_str("2.3.4.5:6789").unwrap();
+        let unaffected_stream_key =
+            StreamKey::new(main_cryptde().public_key().clone(), unaffected_socket_addr);
+        let affected_socket_addr = SocketAddr::from_str("3.4.5.6:7890").unwrap();
+        let affected_stream_key =
+            StreamKey::new(main_cryptde().public_key().clone(), affected_socket_addr);
+        let affected_cryptde = CryptDENull::from(&PublicKey::new(b"affected"), DEFAULT_CHAIN_ID);
         subject
-            .tunneled_hosts
-            .insert(stream_key.clone(), "hostname".to_string());
-        subject.route_ids_to_return_routes.insert(
+            .keys_and_addrs
+            .insert(unaffected_stream_key, unaffected_socket_addr);
+        subject
+            .keys_and_addrs
+            .insert(affected_stream_key, affected_socket_addr);
+        subject.stream_key_routes.insert(
+            unaffected_stream_key,
+            RouteQueryResponse {
+                route: Route { hops: vec![] },
+                expected_services: ExpectedServices::RoundTrip(vec![], vec![], 1234),
+            },
+        );
+        let affected_route = Route::round_trip(
+            RouteSegment::new(
+                vec![main_cryptde().public_key(), affected_cryptde.public_key()],
+                Component::ProxyClient,
+            ),
+            RouteSegment::new(
+                vec![affected_cryptde.public_key(), main_cryptde().public_key()],
+                Component::ProxyServer,
+            ),
+            main_cryptde(),
+            Some(make_paying_wallet(b"consuming")),
             1234,
-            AddReturnRouteMessage {
-                return_route_id: 1234,
-                expected_services: vec![],
-                protocol: ProxyProtocol::HTTP,
-                server_name: None,
+            Some(contract_address(DEFAULT_CHAIN_ID)),
+        )
+        .unwrap();
+        let affected_expected_services = vec![ExpectedService::Exit(
+            affected_cryptde.public_key().clone(),
+            make_paying_wallet(b"1234"),
+            DEFAULT_RATE_PACK,
+        )];
+        subject.stream_key_routes.insert(
+            affected_stream_key,
+            RouteQueryResponse {
+                route: affected_route.clone(),
+                expected_services: ExpectedServices::RoundTrip(
+                    affected_expected_services,
+                    vec![],
+                    1234,
+                ),
+            },
+        );
+        subject.tunneled_hosts.insert(
+            unaffected_stream_key,
+            Host {
+                name: "blah".to_string(),
+                port: Some(TLS_PORT),
+            },
+        );
+        subject.tunneled_hosts.insert(
+            affected_stream_key,
+            Host {
+                name: "tunneled.com".to_string(),
+                port: Some(TLS_PORT),
             },
         );
-
-        let client_response_payload = ClientResponsePayload_0v1 {
-            stream_key: stream_key.clone(),
-            sequenced_packet: SequencedPacket::new(vec![], 1, true),
-        };
-
-        let (dispatcher_mock, _, _) = make_recorder();
-
-        let peer_actors = peer_actors_builder().dispatcher(dispatcher_mock).build();
-
-        subject.subs.as_mut().unwrap().dispatcher = peer_actors.dispatcher.from_dispatcher_client;
-
-        let expired_cores_package: ExpiredCoresPackage<ClientResponsePayload_0v1> =
-            ExpiredCoresPackage::new(
-                SocketAddr::from_str("1.2.3.4:1234").unwrap(),
-                Some(make_wallet("irrelevant")),
-                return_route_with_id(cryptde, 1234),
-                client_response_payload.into(),
-                0,
-            );
-
-        subject.handle_client_response_payload(&expired_cores_package);
-
-        assert!(subject.keys_and_addrs.is_empty());
-        assert!(subject.stream_key_routes.is_empty());
-        assert!(subject.tunneled_hosts.is_empty());
+        let subject_addr = subject.start();
+        let (hopper, _, hopper_recording_arc) = make_recorder();
+        let (proxy_server, _, proxy_server_recording_arc) = make_recorder();
+        let peer_actors = peer_actors_builder()
+            .hopper(hopper)
+            .proxy_server(proxy_server)
+            .build();
+        subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+
+        subject_addr
+            .try_send(StreamShutdownMsg {
+                peer_addr: affected_socket_addr,
+                stream_type: RemovedStreamType::NonClandestine(NonClandestineAttributes {
+                    reception_port: TLS_PORT,
+                    sequence_number: 1234,
+                    half_closed: false,
+                }),
+                report_to_counterpart: true,
+            })
+            .unwrap();
+
+        System::current().stop_with_code(0);
+        system.run();
+        let recording = hopper_recording_arc.lock().unwrap();
+        let record = recording.get_record::<IncipientCoresPackage>(0);
+        assert_eq!(record.route, affected_route);
+        let payload = decodex::<MessageType>(&affected_cryptde, &record.payload).unwrap();
+        match payload {
+            MessageType::ClientRequest(vd) => assert_eq!(
+                vd.extract(&crate::sub_lib::migrations::client_request_payload::MIGRATIONS)
+                    .unwrap(),
+                ClientRequestPayload_0v1 {
+                    stream_key: affected_stream_key,
+                    sequenced_packet: SequencedPacket::new(vec![], 1234, true),
+                    target_hostname: Some(String::from("tunneled.com")),
+                    target_port: 443,
+                    protocol: ProxyProtocol::TLS,
+                    originator_public_key: alias_cryptde().public_key().clone(),
+                    is_resumption: false,
+                }
+            ),
+            other => panic!("Wrong payload type: {:?}", other),
+        }
+        let recording = proxy_server_recording_arc.lock().unwrap();
+        let record = recording.get_record::<StreamShutdownMsg>(recording.len() - 1);
+        assert_eq!(
+            record,
+            &StreamShutdownMsg {
+                peer_addr: affected_socket_addr,
+                stream_type: RemovedStreamType::NonClandestine(NonClandestineAttributes {
+                    reception_port: 0,
+                    sequence_number: 0,
+                    half_closed: false,
+                }),
+                report_to_counterpart: false
+            }
+        );
     }
 
     #[test]
-    fn proxy_server_receives_nonterminal_response_from_hopper() {
-        let system = System::new("proxy_server_receives_response_from_hopper");
-        let (dispatcher_mock, _, dispatcher_log_arc) = make_recorder();
-        let (accountant, _, accountant_recording_arc) = make_recorder();
-        let cryptde = main_cryptde();
+    fn handle_stream_shutdown_msg_reports_to_counterpart_without_tunnel_when_necessary() {
+        let system = System::new("test");
         let mut subject = ProxyServer::new(
-            cryptde,
+            main_cryptde(),
             alias_cryptde(),
-            false,
+            true,
             Some(STANDARD_CONSUMING_WALLET_BALANCE),
+            None,
         );
-        let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
-        let stream_key = make_meaningless_stream_key();
-        let irrelevant_public_key = PublicKey::from(&b"irrelevant"[..]);
+        let unaffected_socket_addr = SocketAddr::from_str("2.3.4.5:6789").unwrap();
+        let unaffected_stream_key =
+            StreamKey::new(main_cryptde().public_key().clone(), unaffected_socket_addr);
+        let affected_socket_addr = SocketAddr::from_str("3.4.5.6:7890").unwrap();
+        let affected_stream_key =
+            StreamKey::new(main_cryptde().public_key().clone(), affected_socket_addr);
+        let affected_cryptde = CryptDENull::from(&PublicKey::new(b"affected"), DEFAULT_CHAIN_ID);
         subject
             .keys_and_addrs
-            .insert(stream_key.clone(), socket_addr.clone());
-        let incoming_route_d_wallet = make_wallet("D Earning");
-        let incoming_route_e_wallet = make_wallet("E Earning");
-        let incoming_route_f_wallet = make_wallet("F Earning");
-        subject.route_ids_to_return_routes.insert(
-            1234,
-            AddReturnRouteMessage {
-                return_route_id: 1234,
-                expected_services: vec![
-                    ExpectedService::Exit(
-                        irrelevant_public_key.clone(),
-                        incoming_route_d_wallet.clone(),
-                        rate_pack(101),
-                    ),
-                    ExpectedService::Routing(
-                        irrelevant_public_key.clone(),
-                        incoming_route_e_wallet.clone(),
-                        rate_pack(102),
-                    ),
-                    ExpectedService::Routing(
-                        irrelevant_public_key.clone(),
-                        incoming_route_f_wallet.clone(),
-                        rate_pack(103),
-                    ),
-                    ExpectedService::Nothing,
-                ],
-                protocol: ProxyProtocol::TLS,
-                server_name: None,
+            .insert(unaffected_stream_key, unaffected_socket_addr);
+        subject
+            .keys_and_addrs
+            .insert(affected_stream_key, affected_socket_addr);
+        subject.stream_key_routes.insert(
+            unaffected_stream_key,
+            RouteQueryResponse {
+                route: Route { hops: vec![] },
+                expected_services: ExpectedServices::RoundTrip(vec![], vec![], 1234),
             },
         );
-        let incoming_route_g_wallet = make_wallet("G Earning");
-        let incoming_route_h_wallet = make_wallet("H Earning");
-        let incoming_route_i_wallet = make_wallet("I Earning");
-        subject.route_ids_to_return_routes.insert(
-            1235,
-            AddReturnRouteMessage {
-                return_route_id: 1235,
-                expected_services: vec![
-                    ExpectedService::Exit(
-                        irrelevant_public_key.clone(),
-                        incoming_route_g_wallet.clone(),
-                        rate_pack(104),
-                    ),
-                    ExpectedService::Routing(
-                        irrelevant_public_key.clone(),
-                        incoming_route_h_wallet.clone(),
-                        rate_pack(105),
-                    ),
-                    ExpectedService::Routing(
-                        irrelevant_public_key.clone(),
-                        incoming_route_i_wallet.clone(),
-                        rate_pack(106),
-                    ),
-                    ExpectedService::Nothing,
-                ],
-                protocol: ProxyProtocol::TLS,
-                server_name: None,
+        let affected_route = Route::round_trip(
+            RouteSegment::new(
+                vec![main_cryptde().public_key(), affected_cryptde.public_key()],
+                Component::ProxyClient,
+            ),
+            RouteSegment::new(
+                vec![affected_cryptde.public_key(), main_cryptde().public_key()],
+                Component::ProxyServer,
+            ),
+            main_cryptde(),
+            Some(make_paying_wallet(b"consuming")),
+            1234,
+            Some(contract_address(DEFAULT_CHAIN_ID)),
+        )
+        .unwrap();
+        let affected_expected_services = vec![ExpectedService::Exit(
+            affected_cryptde.public_key().clone(),
+            make_paying_wallet(b"1234"),
+            DEFAULT_RATE_PACK,
+        )];
+        subject.stream_key_routes.insert(
+            affected_stream_key,
+            RouteQueryResponse {
+                route: affected_route.clone(),
+                expected_services: ExpectedServices::RoundTrip(
+                    affected_expected_services,
+                    vec![],
+                    1234,
+                ),
             },
         );
-        let subject_addr: Addr<ProxyServer> = subject.start();
-        let first_client_response_payload = ClientResponsePayload_0v1 {
-            stream_key,
-            sequenced_packet: SequencedPacket {
-                data: b"some data".to_vec(),
-                sequence_number: 4321,
-                last_data: false,
-            },
-        };
-        let first_exit_size = first_client_response_payload.sequenced_packet.data.len();
-        let first_expired_cores_package: ExpiredCoresPackage<ClientResponsePayload_0v1> =
-            ExpiredCoresPackage::new(
-                SocketAddr::from_str("1.2.3.4:1234").unwrap(),
-                Some(make_wallet("irrelevant")),
-                return_route_with_id(cryptde, 1234),
-                first_client_response_payload.into(),
-                0,
-            );
-        let routing_size = first_expired_cores_package.payload_len;
-
-        let second_client_response_payload = ClientResponsePayload_0v1 {
-            stream_key,
-            sequenced_packet: SequencedPacket {
-                data: b"other data".to_vec(),
-                sequence_number: 4322,
-                last_data: false,
-            },
-        };
-        let second_exit_size = second_client_response_payload.sequenced_packet.data.len();
-        let second_expired_cores_package: ExpiredCoresPackage<ClientResponsePayload_0v1> =
-            ExpiredCoresPackage::new(
-                SocketAddr::from_str("1.2.3.5:1235").unwrap(),
-                Some(make_wallet("irrelevant")),
-                return_route_with_id(cryptde, 1235),
-                second_client_response_payload.into(),
-                0,
-            );
-        let mut peer_actors = peer_actors_builder()
-            .dispatcher(dispatcher_mock)
-            .accountant(accountant)
+        let subject_addr = subject.start();
+        let (hopper, _, hopper_recording_arc) = make_recorder();
+        let (proxy_server, _, proxy_server_recording_arc) = make_recorder();
+        let peer_actors = peer_actors_builder()
+            .hopper(hopper)
+            .proxy_server(proxy_server)
             .build();
-        peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
         subject_addr.try_send(BindMessage { peer_actors }).unwrap();
 
         subject_addr
-            .try_send(first_expired_cores_package.clone())
-            .unwrap();
-        subject_addr
-            .try_send(second_expired_cores_package.clone())
-            .unwrap();
-
-        System::current().stop_with_code(0);
-        system.run();
-
-        let dispatcher_recording = dispatcher_log_arc.lock().unwrap();
-        let record = dispatcher_recording.get_record::<TransmitDataMsg>(0);
-        assert_eq!(record.endpoint, Endpoint::Socket(socket_addr));
-        assert_eq!(record.last_data, false);
-        assert_eq!(record.data, b"some data".to_vec());
-        let record = dispatcher_recording.get_record::<TransmitDataMsg>(1);
-        assert_eq!(record.endpoint, Endpoint::Socket(socket_addr));
-        assert_eq!(record.last_data, false);
-        assert_eq!(record.data, b"other data".to_vec());
-        let accountant_recording = accountant_recording_arc.lock().unwrap();
-
-        check_exit_report(
-            &accountant_recording,
-            0,
-            &incoming_route_d_wallet,
-            first_exit_size,
-        );
-        check_routing_report(
-            &accountant_recording,
-            1,
-            &incoming_route_e_wallet,
-            routing_size,
-        );
-        check_routing_report(
-            &accountant_recording,
-            2,
-            &incoming_route_f_wallet,
-            routing_size,
-        );
-        let routing_size = second_expired_cores_package.payload_len;
-        check_exit_report(
-            &accountant_recording,
-            3,
-            &incoming_route_g_wallet,
-            second_exit_size,
-        );
-        check_routing_report(
-            &accountant_recording,
-            4,
-            &incoming_route_h_wallet,
-            routing_size,
-        );
-        check_routing_report(
-            &accountant_recording,
-            5,
-            &incoming_route_i_wallet,
-            routing_size,
+            .try_send(StreamShutdownMsg {
+                peer_addr: affected_socket_addr,
+                stream_type: RemovedStreamType::NonClandestine(NonClandestineAttributes {
+                    reception_port: HTTP_PORT,
+                    sequence_number: 1234,
+                    half_closed: false,
+                }),
+                report_to_counterpart: true,
+            })
+            .unwrap();
+
+        System::current().stop_with_code(0);
+        system.run();
+        let recording = hopper_recording_arc.lock().unwrap();
+        let record = recording.get_record::<IncipientCoresPackage>(0);
+        assert_eq!(record.route, affected_route);
+        let payload = decodex::<MessageType>(&affected_cryptde, &record.payload).unwrap();
+        match payload {
+            MessageType::ClientRequest(vd) => assert_eq!(
+                vd.extract(&crate::sub_lib::migrations::client_request_payload::MIGRATIONS)
+                    .unwrap(),
+                ClientRequestPayload_0v1 {
+                    stream_key: affected_stream_key,
+                    sequenced_packet: SequencedPacket::new(vec![], 1234, true),
+                    target_hostname: None,
+                    target_port: HTTP_PORT,
+                    protocol: ProxyProtocol::HTTP,
+                    originator_public_key: alias_cryptde().public_key().clone(),
+                    is_resumption: false,
+                }
+            ),
+            other => panic!("Wrong payload type: {:?}", other),
+        }
+        let recording = proxy_server_recording_arc.lock().unwrap();
+        let record = recording.get_record::<StreamShutdownMsg>(recording.len() - 1);
+        assert_eq!(
+            record,
+            &StreamShutdownMsg {
+                peer_addr: affected_socket_addr,
+                stream_type: RemovedStreamType::NonClandestine(NonClandestineAttributes {
+                    reception_port: 0,
+                    sequence_number: 0,
+                    half_closed: false,
+                }),
+                report_to_counterpart: false
+            }
         );
-        assert_eq!(accountant_recording.len(), 6);
     }
 
     #[test]
-    fn handle_dns_resolve_failure_sends_message_to_dispatcher() {
-        let system = System::new("proxy_server_receives_response_from_routing_services");
-
-        let (dispatcher_mock, _, dispatcher_log_arc) = make_recorder();
-
-        let cryptde = main_cryptde();
+    fn handle_stream_shutdown_msg_reports_half_close_to_counterpart_without_retiring_stream_key() {
+        let system = System::new("test");
         let mut subject = ProxyServer::new(
-            cryptde,
+            main_cryptde(),
             alias_cryptde(),
-            false,
+            true,
             Some(STANDARD_CONSUMING_WALLET_BALANCE),
+            None,
         );
-
-        let stream_key = make_meaningless_stream_key();
-        let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
-
+        let unaffected_socket_addr = SocketAddr::from_str("2.3.4.5:6789").unwrap();
+        let unaffected_stream_key =
+            StreamKey::new(main_cryptde().public_key().clone(), unaffected_socket_addr);
+        let affected_socket_addr = SocketAddr::from_str("3.4.5.6:7890").unwrap();
+        let affected_stream_key =
+            StreamKey::new(main_cryptde().public_key().clone(), affected_socket_addr);
+        let affected_cryptde = CryptDENull::from(&PublicKey::new(b"affected"), DEFAULT_CHAIN_ID);
         subject
             .keys_and_addrs
-            .insert(stream_key.clone(), socket_addr.clone());
-
-        let exit_public_key = PublicKey::from(&b"exit_key"[..]);
-        let exit_wallet = make_wallet("exit wallet");
-
-        let subject_addr: Addr<ProxyServer> = subject.start();
-
-        let dns_resolve_failure = DnsResolveFailure_0v1::new(stream_key);
-
-        let expired_cores_package: ExpiredCoresPackage<DnsResolveFailure_0v1> =
-            ExpiredCoresPackage::new(
-                SocketAddr::from_str("1.2.3.4:1234").unwrap(),
-                Some(make_wallet("irrelevant")),
-                return_route_with_id(cryptde, 1234),
-                dns_resolve_failure.into(),
-                0,
-            );
-
-        let mut peer_actors = peer_actors_builder().dispatcher(dispatcher_mock).build();
-        peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
-
+            .insert(unaffected_stream_key, unaffected_socket_addr);
+        subject
+            .keys_and_addrs
+            .insert(affected_stream_key, affected_socket_addr);
+        subject.stream_key_routes.insert(
+            unaffected_stream_key,
+            RouteQueryResponse {
+                route: Route { hops: vec![] },
+                expected_services: ExpectedServices::RoundTrip(vec![], vec![], 1234),
+            },
+        );
+        let affected_route = Route::round_trip(
+            RouteSegment::new(
+                vec![main_cryptde().public_key(), affected_cryptde.public_key()],
+                Component::ProxyClient,
+            ),
+            RouteSegment::new(
+                vec![affected_cryptde.public_key(), main_cryptde().public_key()],
+                Component::ProxyServer,
+            ),
+            main_cryptde(),
+            Some(make_paying_wallet(b"consuming")),
+            1234,
+            Some(contract_address(DEFAULT_CHAIN_ID)),
+        )
+        .unwrap();
+        let affected_expected_services = vec![ExpectedService::Exit(
+            affected_cryptde.public_key().clone(),
+            make_paying_wallet(b"1234"),
+            DEFAULT_RATE_PACK,
+        )];
+        subject.stream_key_routes.insert(
+            affected_stream_key,
+            RouteQueryResponse {
+                route: affected_route.clone(),
+                expected_services: ExpectedServices::RoundTrip(
+                    affected_expected_services,
+                    vec![],
+                    1234,
+                ),
+            },
+        );
+        let subject_addr = subject.start();
+        let (hopper, _, hopper_recording_arc) = make_recorder();
+        let (proxy_server, _, proxy_server_recording_arc) = make_recorder();
+        let peer_actors = peer_actors_builder()
+            .hopper(hopper)
+            .proxy_server(proxy_server)
+            .build();
         subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+
         subject_addr
-            .try_send(AddReturnRouteMessage {
-                return_route_id: 1234,
-                expected_services: vec![ExpectedService::Exit(
-                    exit_public_key.clone(),
-                    exit_wallet,
-                    rate_pack(10),
-                )],
-                protocol: ProxyProtocol::HTTP,
-                server_name: Some("server.com".to_string()),
+            .try_send(StreamShutdownMsg {
+                peer_addr: affected_socket_addr,
+                stream_type: RemovedStreamType::NonClandestine(NonClandestineAttributes {
+                    reception_port: HTTP_PORT,
+                    sequence_number: 1234,
+                    half_closed: true,
+                }),
+                report_to_counterpart: true,
             })
             .unwrap();
 
-        subject_addr.try_send(expired_cores_package).unwrap();
-
         System::current().stop_with_code(0);
         system.run();
-
-        let dispatcher_recording = dispatcher_log_arc.lock().unwrap();
-        let record = dispatcher_recording.get_record::<TransmitDataMsg>(0);
-        assert_eq!(
-            TransmitDataMsg {
-                endpoint: Endpoint::Socket(socket_addr),
-                last_data: true,
-                sequence_number: Some(0),
-                data: ServerImpersonatorHttp {}.dns_resolution_failure_response(
-                    &exit_public_key,
-                    Some("server.com".to_string()),
-                ),
-            },
-            *record
-        );
+        let recording = hopper_recording_arc.lock().unwrap();
+        let record = recording.get_record::<IncipientCoresPackage>(0);
+        assert_eq!(record.route, affected_route);
+        let payload = decodex::<MessageType>(&affected_cryptde, &record.payload).unwrap();
+        match payload {
+            MessageType::ClientRequest(vd) => assert_eq!(
+                vd.extract(&crate::sub_lib::migrations::client_request_payload::MIGRATIONS)
+                    .unwrap(),
+                ClientRequestPayload_0v1 {
+                    stream_key: affected_stream_key,
+                    sequenced_packet: SequencedPacket::new(vec![], 1234, true),
+                    target_hostname: None,
+                    target_port: HTTP_PORT,
+                    protocol: ProxyProtocol::HTTP,
+                    originator_public_key: alias_cryptde().public_key().clone(),
+                    is_resumption: false,
+                }
+            ),
+            other => panic!("Wrong payload type: {:?}", other),
+        }
+        let recording = proxy_server_recording_arc.lock().unwrap();
+        let _ = recording.get_record::<AddReturnRouteMessage>(0); // don't care about this, other than type
+        assert_eq!(recording.len(), 1); // no StreamShutdownMsg to retire the stream key
     }
 
     #[test]
-    fn handle_dns_resolve_failure_reports_services_consumed() {
-        let system = System::new("proxy_server_records_accounting");
-        let (accountant, _, accountant_recording_arc) = make_recorder();
-        let cryptde = main_cryptde();
-        let mut subject = ProxyServer::new(
-            cryptde,
-            alias_cryptde(),
-            false,
-            Some(STANDARD_CONSUMING_WALLET_BALANCE),
-        );
-        let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
-        let stream_key = make_meaningless_stream_key();
-        let irrelevant_public_key = PublicKey::from(&b"irrelevant"[..]);
+    fn handle_stream_shutdown_msg_does_not_report_to_counterpart_when_unnecessary() {
+        let mut subject = ProxyServer::new(main_cryptde(), alias_cryptde(), true, None, None);
+        let unaffected_socket_addr = SocketAddr::from_str("2.3.4.5:6789").unwrap();
+        let unaffected_stream_key =
+            StreamKey::new(main_cryptde().public_key().clone(), unaffected_socket_addr);
+        let affected_socket_addr = SocketAddr::from_str("3.4.5.6:7890").unwrap();
+        let affected_stream_key =
+            StreamKey::new(main_cryptde().public_key().clone(), affected_socket_addr);
         subject
             .keys_and_addrs
-            .insert(stream_key.clone(), socket_addr.clone());
-        let incoming_route_d_wallet = make_wallet("D Earning");
-        let incoming_route_e_wallet = make_wallet("E Earning");
-        let incoming_route_f_wallet = make_wallet("F Earning");
-        subject.route_ids_to_return_routes.insert(
-            1234,
-            AddReturnRouteMessage {
-                return_route_id: 1234,
-                expected_services: vec![
-                    ExpectedService::Exit(
-                        irrelevant_public_key.clone(),
-                        incoming_route_d_wallet.clone(),
-                        rate_pack(101),
-                    ),
-                    ExpectedService::Routing(
-                        irrelevant_public_key.clone(),
-                        incoming_route_e_wallet.clone(),
-                        rate_pack(102),
-                    ),
-                    ExpectedService::Routing(
-                        irrelevant_public_key.clone(),
-                        incoming_route_f_wallet.clone(),
-                        rate_pack(103),
-                    ),
-                    ExpectedService::Nothing,
-                ],
-                protocol: ProxyProtocol::TLS,
-                server_name: Some("server.com".to_string()),
+            .insert(unaffected_stream_key, unaffected_socket_addr);
+        subject
+            .keys_and_addrs
+            .insert(affected_stream_key, affected_socket_addr);
+        subject.stream_key_routes.insert(
+            unaffected_stream_key,
+            RouteQueryResponse {
+                route: Route { hops: vec![] },
+                expected_services: ExpectedServices::RoundTrip(vec![], vec![], 1234),
+            },
+        );
+        subject.stream_key_routes.insert(
+            affected_stream_key,
+            RouteQueryResponse {
+                route: Route { hops: vec![] },
+                expected_services: ExpectedServices::RoundTrip(vec![], vec![], 1234),
+            },
+        );
+        subject.tunneled_hosts.insert(
+            unaffected_stream_key,
+            Host {
+                name: "blah".to_string(),
+                port: Some(TLS_PORT),
+            },
+        );
+        subject.tunneled_hosts.insert(
+            affected_stream_key,
+            Host {
+                name: "blah".to_string(),
+                port: Some(TLS_PORT),
             },
         );
 
-        let subject_addr: Addr<ProxyServer> = subject.start();
-        let dns_resolve_failure_payload = DnsResolveFailure_0v1::new(stream_key);
+        subject.handle_stream_shutdown_msg(StreamShutdownMsg {
+            peer_addr: affected_socket_addr,
+            stream_type: RemovedStreamType::NonClandestine(NonClandestineAttributes {
+                reception_port: HTTP_PORT,
+                sequence_number: 1234,
+                half_closed: false,
+            }),
+            report_to_counterpart: false,
+        });
 
-        let expired_cores_package: ExpiredCoresPackage<DnsResolveFailure_0v1> =
-            ExpiredCoresPackage::new(
-                SocketAddr::from_str("1.2.3.4:1234").unwrap(),
-                Some(make_wallet("irrelevant")),
-                return_route_with_id(cryptde, 1234),
-                dns_resolve_failure_payload.into(),
-                0,
-            );
-        let routing_size = expired_cores_package.payload_len;
+        // Subject is unbound but didn't panic; therefore, no attempt to send to Hopper: perfect!
+        assert!(subject
+            .keys_and_addrs
+            .a_to_b(&unaffected_stream_key)
+            .is_some());
+        assert!(subject
+            .stream_key_routes
+            .contains_key(&unaffected_stream_key));
+        assert!(subject.tunneled_hosts.contains_key(&unaffected_stream_key));
+        assert!(subject
+            .keys_and_addrs
+            .a_to_b(&affected_stream_key)
+            .is_none());
+        assert!(!subject.stream_key_routes.contains_key(&affected_stream_key));
+        assert!(!subject.tunneled_hosts.contains_key(&affected_stream_key));
+    }
 
-        let mut peer_actors = peer_actors_builder().accountant(accountant).build();
-        peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
-        subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+    #[test]
+    #[should_panic(
+        expected = "ProxyServer should never get ShutdownStreamMsg about clandestine stream"
+    )]
+    fn handle_stream_shutdown_complains_about_clandestine_message() {
+        let system = System::new("test");
+        let subject = ProxyServer::new(main_cryptde(), alias_cryptde(), true, None, None);
+        let subject_addr = subject.start();
 
         subject_addr
-            .try_send(expired_cores_package.clone())
+            .try_send(StreamShutdownMsg {
+                peer_addr: SocketAddr::from_str("1.2.3.4:5678").unwrap(),
+                stream_type: RemovedStreamType::Clandestine,
+                report_to_counterpart: false,
+            })
             .unwrap();
 
         System::current().stop_with_code(0);
         system.run();
-
-        let accountant_recording = accountant_recording_arc.lock().unwrap();
-        check_exit_report(&accountant_recording, 0, &incoming_route_d_wallet, 0);
-        check_routing_report(
-            &accountant_recording,
-            1,
-            &incoming_route_e_wallet,
-            routing_size,
-        );
-        check_routing_report(
-            &accountant_recording,
-            2,
-            &incoming_route_f_wallet,
-            routing_size,
-        );
-        assert_eq!(accountant_recording.len(), 3);
     }
 
     #[test]
-    fn handle_dns_resolve_failure_sends_message_to_neighborhood() {
-        let system = System::new("test");
+    fn load_split_tunnel_rules_reads_rules_from_database() {
+        let persistent_config = PersistentConfigurationMock::new()
+            .split_tunnel_rules_result(Ok(Some("example.com,10.0.0.0/8".to_string())));
+        let mut subject = ProxyServer::new(main_cryptde(), alias_cryptde(), true, None, None);
 
-        let (neighborhood_mock, _, neighborhood_log_arc) = make_recorder();
+        subject.load_split_tunnel_rules(Box::new(persistent_config));
 
-        let cryptde = main_cryptde();
-        let mut subject = ProxyServer::new(
-            cryptde,
-            alias_cryptde(),
-            false,
-            Some(STANDARD_CONSUMING_WALLET_BALANCE),
+        assert_eq!(
+            subject.split_tunnel_rules,
+            SplitTunnelRules::from_strs(&["example.com", "10.0.0.0/8"]).unwrap()
         );
+        assert_eq!(subject.persistent_config_opt.is_some(), true);
+    }
 
-        let stream_key = make_meaningless_stream_key();
-        let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
+    #[test]
+    #[should_panic(
+        expected = "Database corrupt: invalid split-tunnel rules 'booga/8': Split-tunnel rule 'booga/8' is not a valid CIDR network"
+    )]
+    fn load_split_tunnel_rules_panics_on_corrupt_rules() {
+        let persistent_config = PersistentConfigurationMock::new()
+            .split_tunnel_rules_result(Ok(Some("booga/8".to_string())));
+        let mut subject = ProxyServer::new(main_cryptde(), alias_cryptde(), true, None, None);
 
-        subject
-            .keys_and_addrs
-            .insert(stream_key.clone(), socket_addr.clone());
+        subject.load_split_tunnel_rules(Box::new(persistent_config));
+    }
 
-        let exit_public_key = PublicKey::from(&b"exit_key"[..]);
-        let exit_wallet = make_wallet("exit wallet");
-        subject.route_ids_to_return_routes.insert(
-            1234,
-            AddReturnRouteMessage {
-                return_route_id: 1234,
-                expected_services: vec![ExpectedService::Exit(
-                    exit_public_key.clone(),
-                    exit_wallet,
-                    rate_pack(10),
-                )],
-                protocol: ProxyProtocol::HTTP,
-                server_name: Some("server.com".to_string()),
-            },
+    #[test]
+    fn split_tunnel_rules_request_is_answered_with_current_rules() {
+        let system = System::new("split_tunnel_rules_request_is_answered_with_current_rules");
+        let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
+        let mut subject = ProxyServer::new(main_cryptde(), alias_cryptde(), true, None, None);
+        subject.split_tunnel_rules = SplitTunnelRules::from_strs(&["example.com"]).unwrap();
+        let subject_addr: Addr<ProxyServer> = subject.start();
+        let peer_actors = peer_actors_builder().ui_gateway(ui_gateway).build();
+        subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+
+        subject_addr
+            .try_send(NodeFromUiMessage {
+                client_id: 1234,
+                body: UiSplitTunnelRulesRequest {}.tmb(4321),
+            })
+            .unwrap();
+
+        System::current().stop();
+        system.run();
+        let ui_gateway_recording = ui_gateway_recording_arc.lock().unwrap();
+        assert_eq!(
+            ui_gateway_recording.get_record::<NodeToUiMessage>(0),
+            &NodeToUiMessage {
+                target: ClientId(1234),
+                body: UiSplitTunnelRulesResponse {
+                    rules: vec!["example.com".to_string()]
+                }
+                .tmb(4321),
+            }
         );
+    }
 
+    #[test]
+    fn set_split_tunnel_rules_request_persists_and_applies_new_rules() {
+        let system = System::new("set_split_tunnel_rules_request_persists_and_applies_new_rules");
+        let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
+        let set_split_tunnel_rules_params_arc = Arc::new(Mutex::new(vec![]));
+        let persistent_config = PersistentConfigurationMock::new()
+            .set_split_tunnel_rules_params(&set_split_tunnel_rules_params_arc)
+            .set_split_tunnel_rules_result(Ok(()))
+            .set_split_tunnel_rules_result(Ok(()));
+        let mut subject = ProxyServer::new(main_cryptde(), alias_cryptde(), true, None, None);
+        subject.persistent_config_opt = Some(Box::new(persistent_config));
         let subject_addr: Addr<ProxyServer> = subject.start();
+        let peer_actors = peer_actors_builder().ui_gateway(ui_gateway).build();
+        subject_addr.try_send(BindMessage { peer_actors }).unwrap();
 
-        let dns_resolve_failure = DnsResolveFailure_0v1::new(stream_key);
-
-        let expired_cores_package: ExpiredCoresPackage<DnsResolveFailure_0v1> =
-            ExpiredCoresPackage::new(
-                SocketAddr::from_str("1.2.3.4:1234").unwrap(),
-                Some(make_wallet("irrelevant")),
-                return_route_with_id(cryptde, 1234),
-                dns_resolve_failure.into(),
-                0,
-            );
+        subject_addr
+            .try_send(NodeFromUiMessage {
+                client_id: 1234,
+                body: UiSetSplitTunnelRulesRequest {
+                    rules: vec!["Example.com".to_string(), "10.0.0.0/8".to_string()],
+                }
+                .tmb(4321),
+            })
+            .unwrap();
+        subject_addr
+            .try_send(NodeFromUiMessage {
+                client_id: 1234,
+                body: UiSplitTunnelRulesRequest {}.tmb(4322),
+            })
+            .unwrap();
+        subject_addr
+            .try_send(NodeFromUiMessage {
+                client_id: 1234,
+                body: UiSetSplitTunnelRulesRequest { rules: vec![] }.tmb(4323),
+            })
+            .unwrap();
 
-        let mut peer_actors = peer_actors_builder()
-            .neighborhood(neighborhood_mock)
-            .build();
-        peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
+        System::current().stop();
+        system.run();
+        let ui_gateway_recording = ui_gateway_recording_arc.lock().unwrap();
+        assert_eq!(
+            ui_gateway_recording.get_record::<NodeToUiMessage>(0).body,
+            UiSetSplitTunnelRulesResponse {}.tmb(4321)
+        );
+        assert_eq!(
+            ui_gateway_recording.get_record::<NodeToUiMessage>(1).body,
+            UiSplitTunnelRulesResponse {
+                rules: vec!["example.com".to_string(), "10.0.0.0/8".to_string()]
+            }
+            .tmb(4322)
+        );
+        assert_eq!(
+            ui_gateway_recording.get_record::<NodeToUiMessage>(2).body,
+            UiSetSplitTunnelRulesResponse {}.tmb(4323)
+        );
+        let set_split_tunnel_rules_params = set_split_tunnel_rules_params_arc.lock().unwrap();
+        assert_eq!(
+            *set_split_tunnel_rules_params,
+            vec![Some("example.com,10.0.0.0/8".to_string()), None]
+        );
+    }
 
+    #[test]
+    fn set_split_tunnel_rules_request_rejects_bad_rules_and_keeps_old_ones() {
+        let system =
+            System::new("set_split_tunnel_rules_request_rejects_bad_rules_and_keeps_old_ones");
+        let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
+        let mut subject = ProxyServer::new(main_cryptde(), alias_cryptde(), true, None, None);
+        subject.split_tunnel_rules = SplitTunnelRules::from_strs(&["example.com"]).unwrap();
+        subject.persistent_config_opt = Some(Box::new(PersistentConfigurationMock::new()));
+        let subject_addr: Addr<ProxyServer> = subject.start();
+        let peer_actors = peer_actors_builder().ui_gateway(ui_gateway).build();
         subject_addr.try_send(BindMessage { peer_actors }).unwrap();
 
-        subject_addr.try_send(expired_cores_package).unwrap();
+        subject_addr
+            .try_send(NodeFromUiMessage {
+                client_id: 1234,
+                body: UiSetSplitTunnelRulesRequest {
+                    rules: vec!["booga.com".to_string(), "10.0.0.0/33".to_string()],
+                }
+                .tmb(4321),
+            })
+            .unwrap();
+        subject_addr
+            .try_send(NodeFromUiMessage {
+                client_id: 1234,
+                body: UiSplitTunnelRulesRequest {}.tmb(4322),
+            })
+            .unwrap();
 
         System::current().stop();
         system.run();
-
-        let neighborhood_recording = neighborhood_log_arc.lock().unwrap();
-        let record = neighborhood_recording.get_record::<NodeRecordMetadataMessage>(0);
+        let ui_gateway_recording = ui_gateway_recording_arc.lock().unwrap();
         assert_eq!(
-            record,
-            &NodeRecordMetadataMessage::Desirable(exit_public_key, false)
+            ui_gateway_recording.get_record::<NodeToUiMessage>(0).body,
+            MessageBody {
+                opcode: "setSplitTunnelRules".to_string(),
+                path: MessagePath::Conversation(4321),
+                payload: Err((
+                    SPLIT_TUNNEL_RULES_ERROR,
+                    "Split-tunnel rule '10.0.0.0/33' has an invalid network prefix".to_string()
+                )),
+            }
+        );
+        assert_eq!(
+            ui_gateway_recording.get_record::<NodeToUiMessage>(1).body,
+            UiSplitTunnelRulesResponse {
+                rules: vec!["example.com".to_string()]
+            }
+            .tmb(4322)
         );
     }
 
     #[test]
-    fn handle_dns_resolve_failure_logs_when_stream_key_be_gone_but_server_name_be_not() {
-        init_test_logging();
-        let system = System::new("test");
+    fn split_tunnel_rules_send_only_matching_hosts_through_masq() {
+        let (neighborhood_mock, neighborhood_awaiter, neighborhood_recording_arc) = make_recorder();
+        let neighborhood_mock = neighborhood_mock
+            .route_query_response(None)
+            .route_query_response(None);
+        let make_msg = |peer_addr: &str, hostname: &str| InboundClientData {
+            peer_addr: SocketAddr::from_str(peer_addr).unwrap(),
+            reception_port: Some(HTTP_PORT),
+            sequence_number: Some(0),
+            last_data: false,
+            is_clandestine: false,
+            data: format!("GET /index.html HTTP/1.1\r\nHost: {}\r\n\r\n", hostname).into_bytes(),
+        };
+        let proxied_msg = make_msg("1.2.3.4:5678", "www.example.com");
+        let bypassed_msg = make_msg("1.2.3.4:5679", "example.org");
 
-        let (neighborhood_mock, _, _) = make_recorder();
+        thread::spawn(move || {
+            let system = System::new("split_tunnel_rules_send_only_matching_hosts_through_masq");
+            let mut subject = ProxyServer::new(
+                main_cryptde(),
+                alias_cryptde(),
+                true,
+                Some(STANDARD_CONSUMING_WALLET_BALANCE),
+                None,
+            );
+            subject.split_tunnel_rules = SplitTunnelRules::from_strs(&["example.com"]).unwrap();
+            let subject_addr: Addr<ProxyServer> = subject.start();
+            let mut peer_actors = peer_actors_builder()
+                .neighborhood(neighborhood_mock)
+                .build();
+            peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
+            subject_addr.try_send(BindMessage { peer_actors }).unwrap();
 
-        let cryptde = main_cryptde();
-        let mut subject = ProxyServer::new(
-            cryptde,
-            alias_cryptde(),
-            false,
-            Some(STANDARD_CONSUMING_WALLET_BALANCE),
+            subject_addr.try_send(proxied_msg).unwrap();
+            subject_addr.try_send(bypassed_msg).unwrap();
+
+            system.run();
+        });
+
+        neighborhood_awaiter.await_message_count(2);
+        let neighborhood_recording = neighborhood_recording_arc.lock().unwrap();
+        assert_eq!(
+            neighborhood_recording.get_record::<RouteQueryMessage>(0),
+            &RouteQueryMessage::data_indefinite_route_request(DEFAULT_MINIMUM_HOP_COUNT)
+                .with_exit_target(Some("www.example.com".to_string()), 80)
+        );
+        assert_eq!(
+            neighborhood_recording.get_record::<RouteQueryMessage>(1),
+            &RouteQueryMessage::data_indefinite_route_request(0)
+                .with_exit_target(Some("example.org".to_string()), 80)
         );
+    }
 
-        let stream_key = make_meaningless_stream_key();
-        let return_route_id = 1234;
-        let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
+    struct ProcessIdentifierMock {
+        applications: HashMap<SocketAddr, String>,
+    }
 
-        subject
-            .keys_and_addrs
-            .insert(stream_key.clone(), socket_addr.clone());
+    impl ProcessIdentifier for ProcessIdentifierMock {
+        fn application(&self, client_addr: SocketAddr) -> Option<String> {
+            self.applications.get(&client_addr).cloned()
+        }
+    }
 
-        let exit_public_key = PublicKey::from(&b"exit_key"[..]);
-        let exit_wallet = make_wallet("exit wallet");
-        subject.route_ids_to_return_routes.insert(
-            return_route_id,
-            AddReturnRouteMessage {
-                return_route_id,
-                expected_services: vec![ExpectedService::Exit(
-                    exit_public_key.clone(),
-                    exit_wallet,
-                    rate_pack(10),
-                )],
-                protocol: ProxyProtocol::HTTP,
-                server_name: Some("server.com".to_string()),
-            },
-        );
+    impl ProcessIdentifierMock {
+        fn new(applications: Vec<(&str, &str)>) -> ProcessIdentifierMock {
+            ProcessIdentifierMock {
+                applications: applications
+                    .into_iter()
+                    .map(|(addr, app)| (SocketAddr::from_str(addr).unwrap(), app.to_string()))
+                    .collect(),
+            }
+        }
+    }
 
-        let subject_addr: Addr<ProxyServer> = subject.start();
+    #[test]
+    fn load_app_rules_reads_rules_from_database() {
+        let persistent_config = PersistentConfigurationMock::new()
+            .application_rules_result(Ok(Some("firefox:masq,curl:block".to_string())));
+        let mut subject = ProxyServer::new(main_cryptde(), alias_cryptde(), true, None, None);
 
-        let dns_resolve_failure = DnsResolveFailure_0v1::new(stream_key);
+        subject.load_app_rules(&persistent_config);
 
-        let expired_cores_package: ExpiredCoresPackage<DnsResolveFailure_0v1> =
-            ExpiredCoresPackage::new(
-                SocketAddr::from_str("1.2.3.4:1234").unwrap(),
-                Some(make_wallet("irrelevant")),
-                return_route_with_id(cryptde, return_route_id),
-                dns_resolve_failure.into(),
-                0,
-            );
+        assert_eq!(
+            subject.app_rules,
+            AppRules::from_strs(&["firefox:masq", "curl:block"]).unwrap()
+        );
+    }
 
-        let already_used_expired_cores_package = expired_cores_package.clone();
+    #[test]
+    #[should_panic(
+        expected = "Database corrupt: invalid application rules 'firefox': Application rule 'firefox' should look like <application>:<action>"
+    )]
+    fn load_app_rules_panics_on_corrupt_rules() {
+        let persistent_config = PersistentConfigurationMock::new()
+            .application_rules_result(Ok(Some("firefox".to_string())));
+        let mut subject = ProxyServer::new(main_cryptde(), alias_cryptde(), true, None, None);
 
-        let mut peer_actors = peer_actors_builder()
-            .neighborhood(neighborhood_mock)
-            .build();
-        peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
+        subject.load_app_rules(&persistent_config);
+    }
 
+    #[test]
+    fn set_application_rules_request_persists_and_applies_new_rules() {
+        let system = System::new("set_application_rules_request_persists_and_applies_new_rules");
+        let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
+        let set_application_rules_params_arc = Arc::new(Mutex::new(vec![]));
+        let persistent_config = PersistentConfigurationMock::new()
+            .set_application_rules_params(&set_application_rules_params_arc)
+            .set_application_rules_result(Ok(()));
+        let mut subject = ProxyServer::new(main_cryptde(), alias_cryptde(), true, None, None);
+        subject.persistent_config_opt = Some(Box::new(persistent_config));
+        let subject_addr: Addr<ProxyServer> = subject.start();
+        let peer_actors = peer_actors_builder().ui_gateway(ui_gateway).build();
         subject_addr.try_send(BindMessage { peer_actors }).unwrap();
 
-        subject_addr.try_send(expired_cores_package).unwrap();
-
         subject_addr
-            .try_send(already_used_expired_cores_package)
+            .try_send(NodeFromUiMessage {
+                client_id: 1234,
+                body: UiSetApplicationRulesRequest {
+                    rules: vec!["Firefox.exe:masq".to_string(), "curl:block".to_string()],
+                }
+                .tmb(4321),
+            })
+            .unwrap();
+        subject_addr
+            .try_send(NodeFromUiMessage {
+                client_id: 1234,
+                body: UiApplicationRulesRequest {}.tmb(4322),
+            })
+            .unwrap();
+        subject_addr
+            .try_send(NodeFromUiMessage {
+                client_id: 1234,
+                body: UiSetApplicationRulesRequest {
+                    rules: vec!["curl:sideways".to_string()],
+                }
+                .tmb(4323),
+            })
             .unwrap();
 
-        System::current().stop_with_code(0);
+        System::current().stop();
         system.run();
-
-        TestLogHandler::new().exists_log_containing(
-            format!("Discarding DnsResolveFailure message for \"server.com\" from an unrecognized stream key {:?}", stream_key).as_str());
+        let ui_gateway_recording = ui_gateway_recording_arc.lock().unwrap();
+        assert_eq!(
+            ui_gateway_recording.get_record::<NodeToUiMessage>(0).body,
+            UiSetApplicationRulesResponse {}.tmb(4321)
+        );
+        assert_eq!(
+            ui_gateway_recording.get_record::<NodeToUiMessage>(1).body,
+            UiApplicationRulesResponse {
+                rules: vec!["firefox:masq".to_string(), "curl:block".to_string()]
+            }
+            .tmb(4322)
+        );
+        assert_eq!(
+            ui_gateway_recording.get_record::<NodeToUiMessage>(2).body,
+            MessageBody {
+                opcode: "setApplicationRules".to_string(),
+                path: MessagePath::Conversation(4323),
+                payload: Err((
+                    APPLICATION_RULES_ERROR,
+                    "Application action 'sideways' is unknown: use masq, direct, or block"
+                        .to_string()
+                )),
+            }
+        );
+        let set_application_rules_params = set_application_rules_params_arc.lock().unwrap();
+        assert_eq!(
+            *set_application_rules_params,
+            vec![Some("firefox:masq,curl:block".to_string())]
+        );
     }
 
     #[test]
-    fn handle_dns_resolve_failure_logs_when_stream_key_and_server_name_are_both_missing() {
-        init_test_logging();
-        let system = System::new("test");
-
-        let (neighborhood_mock, _, _) = make_recorder();
-
-        let cryptde = main_cryptde();
+    fn connections_from_blocked_applications_are_closed() {
+        let system = System::new("connections_from_blocked_applications_are_closed");
+        let (dispatcher, _, dispatcher_recording_arc) = make_recorder();
+        let (neighborhood, _, neighborhood_recording_arc) = make_recorder();
         let mut subject = ProxyServer::new(
-            cryptde,
+            main_cryptde(),
             alias_cryptde(),
-            false,
+            true,
             Some(STANDARD_CONSUMING_WALLET_BALANCE),
+            None,
         );
-
-        let stream_key = make_meaningless_stream_key();
-        let return_route_id = 1234;
-        let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
-
-        subject
-            .keys_and_addrs
-            .insert(stream_key.clone(), socket_addr.clone());
-
-        let exit_public_key = PublicKey::from(&b"exit_key"[..]);
-        let exit_wallet = make_wallet("exit wallet");
-        subject.route_ids_to_return_routes.insert(
-            return_route_id,
-            AddReturnRouteMessage {
-                return_route_id,
-                expected_services: vec![ExpectedService::Exit(
-                    exit_public_key.clone(),
-                    exit_wallet,
-                    rate_pack(10),
-                )],
-                protocol: ProxyProtocol::HTTP,
-                server_name: None,
-            },
-        );
-
+        subject.app_rules = AppRules::from_strs(&["curl:block"]).unwrap();
+        subject.process_identifier =
+            Box::new(ProcessIdentifierMock::new(vec![("127.0.0.1:5678", "curl")]));
         let subject_addr: Addr<ProxyServer> = subject.start();
-
-        let dns_resolve_failure = DnsResolveFailure_0v1::new(stream_key);
-
-        let expired_cores_package: ExpiredCoresPackage<DnsResolveFailure_0v1> =
-            ExpiredCoresPackage::new(
-                SocketAddr::from_str("1.2.3.4:1234").unwrap(),
-                Some(make_wallet("irrelevant")),
-                return_route_with_id(cryptde, return_route_id),
-                dns_resolve_failure.into(),
-                0,
-            );
-
-        let already_used_expired_cores_package = expired_cores_package.clone();
-
         let mut peer_actors = peer_actors_builder()
-            .neighborhood(neighborhood_mock)
+            .dispatcher(dispatcher)
+            .neighborhood(neighborhood)
             .build();
         peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
-
         subject_addr.try_send(BindMessage { peer_actors }).unwrap();
 
-        subject_addr.try_send(expired_cores_package).unwrap();
-
         subject_addr
-            .try_send(already_used_expired_cores_package)
+            .try_send(InboundClientData {
+                peer_addr: SocketAddr::from_str("127.0.0.1:5678").unwrap(),
+                reception_port: Some(HTTP_PORT),
+                sequence_number: Some(0),
+                last_data: false,
+                is_clandestine: false,
+                data: b"GET /index.html HTTP/1.1\r\nHost: example.com\r\n\r\n".to_vec(),
+            })
             .unwrap();
 
-        System::current().stop_with_code(0);
+        System::current().stop();
         system.run();
-
-        TestLogHandler::new().exists_log_containing(
-            format!("Discarding DnsResolveFailure message for <unspecified server> from an unrecognized stream key {:?}", stream_key).as_str());
+        let dispatcher_recording = dispatcher_recording_arc.lock().unwrap();
+        assert_eq!(
+            dispatcher_recording.get_record::<TransmitDataMsg>(0),
+            &TransmitDataMsg {
+                endpoint: Endpoint::Socket(SocketAddr::from_str("127.0.0.1:5678").unwrap()),
+                last_data: true,
+                sequence_number: Some(0),
+                data: vec![],
+                relayed_from_opt: None,
+            }
+        );
+        assert_eq!(neighborhood_recording_arc.lock().unwrap().len(), 0);
     }
 
     #[test]
-    fn handle_dns_resolve_failure_purges_stream_keys() {
-        let cryptde = main_cryptde();
-        let (neighborhood_mock, _, _) = make_recorder();
-        let (dispatcher_mock, _, _) = make_recorder();
+    fn app_rules_override_split_tunnel_rules() {
+        let (neighborhood_mock, neighborhood_awaiter, neighborhood_recording_arc) = make_recorder();
+        let neighborhood_mock = neighborhood_mock
+            .route_query_response(None)
+            .route_query_response(None)
+            .route_query_response(None);
+        let make_msg = |peer_addr: &str, hostname: &str| InboundClientData {
+            peer_addr: SocketAddr::from_str(peer_addr).unwrap(),
+            reception_port: Some(HTTP_PORT),
+            sequence_number: Some(0),
+            last_data: false,
+            is_clandestine: false,
+            data: format!("GET /index.html HTTP/1.1\r\nHost: {}\r\n\r\n", hostname).into_bytes(),
+        };
+        let direct_msg = make_msg("127.0.0.1:5678", "www.example.com");
+        let masq_msg = make_msg("127.0.0.1:5679", "example.org");
+        let reported_msg = make_msg("192.168.0.5:5680", "example.org");
 
-        let mut subject = ProxyServer::new(
-            cryptde,
-            alias_cryptde(),
-            false,
-            Some(STANDARD_CONSUMING_WALLET_BALANCE),
-        );
-        subject.subs = Some(ProxyServerOutSubs::default());
+        thread::spawn(move || {
+            let system = System::new("app_rules_override_split_tunnel_rules");
+            let mut subject = ProxyServer::new(
+                main_cryptde(),
+                alias_cryptde(),
+                true,
+                Some(STANDARD_CONSUMING_WALLET_BALANCE),
+                None,
+            );
+            subject.split_tunnel_rules = SplitTunnelRules::from_strs(&["example.com"]).unwrap();
+            subject.app_rules = AppRules::from_strs(&["updater:direct", "firefox:masq"]).unwrap();
+            subject.process_identifier = Box::new(ProcessIdentifierMock::new(vec![
+                ("127.0.0.1:5678", "updater"),
+                ("127.0.0.1:5679", "firefox"),
+            ]));
+            let subject_addr: Addr<ProxyServer> = subject.start();
+            let mut peer_actors = peer_actors_builder()
+                .neighborhood(neighborhood_mock)
+                .build();
+            peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
+            subject_addr.try_send(BindMessage { peer_actors }).unwrap();
 
-        let peer_actors = peer_actors_builder()
-            .neighborhood(neighborhood_mock)
-            .dispatcher(dispatcher_mock)
-            .build();
-        subject.subs.as_mut().unwrap().update_node_record_metadata =
-            peer_actors.neighborhood.update_node_record_metadata;
-        subject.subs.as_mut().unwrap().dispatcher = peer_actors.dispatcher.from_dispatcher_client;
+            subject_addr.try_send(direct_msg).unwrap();
+            subject_addr.try_send(masq_msg).unwrap();
+            subject_addr
+                .try_send(NodeFromUiMessage {
+                    client_id: 1234,
+                    body: UiApplicationOriginReport {
+                        client_address: "192.168.0.5:5680".to_string(),
+                        application: "Firefox.exe".to_string(),
+                    }
+                    .tmb(0),
+                })
+                .unwrap();
+            subject_addr.try_send(reported_msg).unwrap();
 
-        let stream_key = make_meaningless_stream_key();
-        let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
-        subject
-            .keys_and_addrs
-            .insert(stream_key.clone(), socket_addr.clone());
-        subject
-            .tunneled_hosts
-            .insert(stream_key.clone(), "tunneled host".to_string());
-        subject.stream_key_routes.insert(
-            stream_key.clone(),
-            RouteQueryResponse {
-                route: Route { hops: vec![] },
-                expected_services: ExpectedServices::OneWay(vec![]),
-            },
+            system.run();
+        });
+
+        neighborhood_awaiter.await_message_count(3);
+        let neighborhood_recording = neighborhood_recording_arc.lock().unwrap();
+        assert_eq!(
+            neighborhood_recording.get_record::<RouteQueryMessage>(0),
+            &RouteQueryMessage::data_indefinite_route_request(0)
+                .with_exit_target(Some("www.example.com".to_string()), 80)
         );
-        subject.route_ids_to_return_routes.insert(
-            1234,
-            AddReturnRouteMessage {
-                return_route_id: 1234,
-                expected_services: vec![ExpectedService::Nothing, ExpectedService::Nothing],
-                protocol: ProxyProtocol::HTTP,
-                server_name: None,
-            },
+        assert_eq!(
+            neighborhood_recording.get_record::<RouteQueryMessage>(1),
+            &RouteQueryMessage::data_indefinite_route_request(DEFAULT_MINIMUM_HOP_COUNT)
+                .with_exit_target(Some("example.org".to_string()), 80)
         );
-        let dns_resolve_failure = DnsResolveFailure_0v1::new(stream_key);
+        assert_eq!(
+            neighborhood_recording.get_record::<RouteQueryMessage>(2),
+            &RouteQueryMessage::data_indefinite_route_request(DEFAULT_MINIMUM_HOP_COUNT)
+                .with_exit_target(Some("example.org".to_string()), 80)
+        );
+    }
 
-        let expired_cores_package: ExpiredCoresPackage<DnsResolveFailure_0v1> =
-            ExpiredCoresPackage::new(
-                SocketAddr::from_str("1.2.3.4:1234").unwrap(),
-                Some(make_wallet("irrelevant")),
-                return_route_with_id(cryptde, 1234),
-                dns_resolve_failure.into(),
-                0,
+    #[test]
+    fn stalled_get_is_replayed_on_a_new_route_under_a_new_stream_key() {
+        init_test_logging();
+        let (hopper_mock, hopper_awaiter, hopper_recording_arc) = make_recorder();
+        let (neighborhood_mock, neighborhood_awaiter, neighborhood_recording_arc) = make_recorder();
+        let neighborhood_mock = neighborhood_mock.route_query_response(Some(
+            make_round_trip_route_query_response(vec![PublicKey::new(&[1]), PublicKey::new(&[2])]),
+        ));
+        let stream_key = make_meaningless_stream_key();
+        let request_data = b"GET /index.html HTTP/1.1\r\nHost: example.com\r\n\r\n".to_vec();
+        let request_data_inner = request_data.clone();
+        thread::spawn(move || {
+            let system =
+                System::new("stalled_get_is_replayed_on_a_new_route_under_a_new_stream_key");
+            let mut subject = ProxyServer::new(
+                main_cryptde(),
+                alias_cryptde(),
+                true,
+                Some(STANDARD_CONSUMING_WALLET_BALANCE),
+                None,
             );
+            subject.stream_key_factory =
+                Box::new(StreamKeyFactoryMock::new().make_result(stream_key));
+            subject.stream_replay_timeout = Duration::from_millis(0);
+            let subject_addr: Addr<ProxyServer> = subject.start();
+            let mut peer_actors = peer_actors_builder()
+                .hopper(hopper_mock)
+                .neighborhood(neighborhood_mock)
+                .build();
+            peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
+            subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+            subject_addr
+                .try_send(InboundClientData {
+                    peer_addr: SocketAddr::from_str("1.2.3.4:5678").unwrap(),
+                    reception_port: Some(HTTP_PORT),
+                    sequence_number: Some(0),
+                    last_data: false,
+                    is_clandestine: false,
+                    data: request_data_inner,
+                })
+                .unwrap();
 
-        subject.handle_dns_resolve_failure(&expired_cores_package);
+            system.run();
+        });
 
-        assert!(subject.keys_and_addrs.is_empty());
-        assert!(subject.stream_key_routes.is_empty());
-        assert!(subject.tunneled_hosts.is_empty());
+        hopper_awaiter.await_message_count(2);
+        neighborhood_awaiter.await_message_count(3);
+        let exit_cryptde = CryptDENull::from(&PublicKey::new(b"exit"), DEFAULT_CHAIN_ID);
+        let hopper_recording = hopper_recording_arc.lock().unwrap();
+        let stream_key_of = |index: usize| {
+            let record = hopper_recording.get_record::<IncipientCoresPackage>(index);
+            match decodex::<MessageType>(&exit_cryptde, &record.payload).unwrap() {
+                MessageType::ClientRequest(vd) => {
+                    let payload = vd
+                        .extract(&crate::sub_lib::migrations::client_request_payload::MIGRATIONS)
+                        .unwrap();
+                    assert_eq!(payload.sequenced_packet.data, request_data);
+                    payload.stream_key
+                }
+                other => panic!("Wrong payload type: {:?}", other),
+            }
+        };
+        assert_eq!(stream_key_of(0), stream_key);
+        assert_eq!(stream_key_of(1), stream_key.retried(1));
+        let neighborhood_recording = neighborhood_recording_arc.lock().unwrap();
+        assert_eq!(
+            neighborhood_recording.get_record::<NodeRecordMetadataMessage>(1),
+            &NodeRecordMetadataMessage::Desirable(PublicKey::new(b"exit"), false)
+        );
+        neighborhood_recording.get_record::<RouteQueryMessage>(2);
+        TestLogHandler::new().exists_log_containing(&format!(
+            "INFO: ProxyServer: Replaying request from 1.2.3.4:5678 on a new route as stream {}: stream {} got no response in 0ns",
+            stream_key.retried(1),
+            stream_key
+        ));
+    }
+
+    #[test]
+    fn only_first_requests_that_change_nothing_are_replayable() {
+        let subject = ProxyServer::new(
+            main_cryptde(),
+            alias_cryptde(),
+            true,
+            Some(STANDARD_CONSUMING_WALLET_BALANCE),
+            None,
+        );
+        let stream_key = make_meaningless_stream_key();
+        let ibcd = |port: u16, sequence_number: u64, data: &[u8]| InboundClientData {
+            peer_addr: SocketAddr::from_str("1.2.3.4:5678").unwrap(),
+            reception_port: Some(port),
+            sequence_number: Some(sequence_number),
+            last_data: false,
+            is_clandestine: false,
+            data: data.to_vec(),
+        };
+        let client_hello = [0x16, 0x03, 0x01, 0x00, 0x05, 0x01, 0x00, 0x00, 0x01, 0x03];
+        let application_data = [0x17, 0x03, 0x03, 0x00, 0x05, 0x01, 0x02, 0x03, 0x04, 0x05];
+
+        assert_eq!(
+            subject.is_replayable(&ibcd(HTTP_PORT, 0, b"GET / HTTP/1.1\r\n\r\n"), &stream_key),
+            true
+        );
+        assert_eq!(
+            subject.is_replayable(&ibcd(HTTP_PORT, 0, b"HEAD / HTTP/1.1\r\n\r\n"), &stream_key),
+            true
+        );
+        assert_eq!(
+            subject.is_replayable(&ibcd(HTTP_PORT, 0, b"POST / HTTP/1.1\r\n\r\n"), &stream_key),
+            false
+        );
+        assert_eq!(
+            subject.is_replayable(&ibcd(HTTP_PORT, 1, b"GET / HTTP/1.1\r\n\r\n"), &stream_key),
+            false
+        );
+        assert_eq!(
+            subject.is_replayable(&ibcd(TLS_PORT, 0, &client_hello), &stream_key),
+            true
+        );
+        assert_eq!(
+            subject.is_replayable(&ibcd(TLS_PORT, 0, &application_data), &stream_key),
+            false
+        );
     }
 
     #[test]
-    #[should_panic(expected = "Dispatcher unbound in ProxyServer")]
-    fn panics_if_dispatcher_is_unbound() {
-        let system = System::new("panics_if_dispatcher_is_unbound");
-        let cryptde = main_cryptde();
-        let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
-        let stream_key = make_meaningless_stream_key();
+    fn stalled_zero_hop_stream_is_not_replayed() {
         let mut subject = ProxyServer::new(
-            cryptde,
+            main_cryptde(),
             alias_cryptde(),
             false,
             Some(STANDARD_CONSUMING_WALLET_BALANCE),
+            None,
         );
-        subject
-            .keys_and_addrs
-            .insert(stream_key.clone(), socket_addr.clone());
-        let remaining_route = return_route_with_id(cryptde, 4321);
-        subject.route_ids_to_return_routes.insert(
-            4321,
-            AddReturnRouteMessage {
-                return_route_id: 4321,
-                expected_services: vec![ExpectedService::Nothing],
-                protocol: ProxyProtocol::HTTP,
-                server_name: None,
+        let stream_key = make_meaningless_stream_key();
+        let now = Instant::now();
+        subject.stream_key_routes.insert(
+            stream_key,
+            RouteQueryResponse {
+                route: Route { hops: vec![] },
+                expected_services: ExpectedServices::RoundTrip(
+                    vec![ExpectedService::Nothing, ExpectedService::Nothing],
+                    vec![ExpectedService::Nothing, ExpectedService::Nothing],
+                    0,
+                ),
             },
         );
-        let subject_addr: Addr<ProxyServer> = subject.start();
-
-        let client_response_payload = ClientResponsePayload_0v1 {
+        subject.replayable_requests.insert(
             stream_key,
-            sequenced_packet: SequencedPacket {
-                data: b"data".to_vec(),
-                sequence_number: 0,
-                last_data: true,
+            ReplayableRequest {
+                msg: InboundClientData {
+                    peer_addr: SocketAddr::from_str("1.2.3.4:5678").unwrap(),
+                    reception_port: Some(HTTP_PORT),
+                    sequence_number: Some(0),
+                    last_data: false,
+                    is_clandestine: false,
+                    data: b"GET / HTTP/1.1\r\n\r\n".to_vec(),
+                },
+                sent_at: now,
+                replays: 0,
             },
-        };
-        let expired_cores_package = ExpiredCoresPackage::new(
-            SocketAddr::from_str("1.2.3.4:1234").unwrap(),
-            Some(make_wallet("consuming")),
-            remaining_route,
-            client_response_payload,
-            0,
         );
 
-        subject_addr.try_send(expired_cores_package).unwrap();
+        subject.replay_stalled_requests(now + STREAM_REPLAY_TIMEOUT);
 
-        System::current().stop_with_code(0);
-        system.run();
+        assert!(subject.replayable_requests.is_empty());
+        assert!(subject.stream_key_routes.contains_key(&stream_key));
     }
 
-    #[test]
-    #[should_panic(expected = "Neighborhood unbound in ProxyServer")]
-    fn panics_if_hopper_is_unbound() {
-        let system = System::new("panics_if_hopper_is_unbound");
-        let http_request = b"GET /index.html HTTP/1.1\r\nHost: nowhere.com\r\n\r\n";
-        let subject = ProxyServer::new(main_cryptde(), alias_cryptde(), false, None);
-        let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
-        let expected_data = http_request.to_vec();
-        let msg_from_dispatcher = InboundClientData {
-            peer_addr: socket_addr.clone(),
-            reception_port: Some(53),
-            sequence_number: Some(0),
-            last_data: false,
-            is_clandestine: false,
-            data: expected_data.clone(),
-        };
-        let subject_addr: Addr<ProxyServer> = subject.start();
+    struct ResumptionStoreMock {
+        save_params: Arc<Mutex<Vec<Vec<ResumptionToken>>>>,
+        load_result: Vec<ResumptionToken>,
+    }
 
-        subject_addr.try_send(msg_from_dispatcher).unwrap();
+    impl ResumptionStore for ResumptionStoreMock {
+        fn save(&self, tokens: &[ResumptionToken]) {
+            self.save_params.lock().unwrap().push(tokens.to_vec());
+        }
 
-        System::current().stop_with_code(0);
-        system.run();
+        fn load(&self) -> Vec<ResumptionToken> {
+            self.load_result.clone()
+        }
+    }
+
+    impl ResumptionStoreMock {
+        fn new() -> ResumptionStoreMock {
+            ResumptionStoreMock {
+                save_params: Arc::new(Mutex::new(vec![])),
+                load_result: vec![],
+            }
+        }
+
+        fn save_params(mut self, params: &Arc<Mutex<Vec<Vec<ResumptionToken>>>>) -> Self {
+            self.save_params = params.clone();
+            self
+        }
+
+        fn load_result(mut self, result: Vec<ResumptionToken>) -> Self {
+            self.load_result = result;
+            self
+        }
+    }
+
+    fn make_resumption_token(stream_key: StreamKey, expires_at: i64) -> ResumptionToken {
+        ResumptionToken {
+            stream_key,
+            exit_key: PublicKey::new(b"exit"),
+            hostname: "example.com".to_string(),
+            port: HTTP_PORT,
+            next_request_sequence: 3,
+            next_response_sequence: 12,
+            expires_at,
+        }
     }
 
     #[test]
-    fn report_response_services_consumed_complains_and_drops_package_if_return_route_id_is_unrecognized(
-    ) {
+    fn load_resumption_tokens_keeps_unexpired_tokens_and_empties_the_store() {
         init_test_logging();
-        let cryptde = main_cryptde();
-        let (dispatcher, _, dispatcher_recording_arc) = make_recorder();
-        let (accountant, _, accountant_recording_arc) = make_recorder();
-        let system = System::new("report_response_services_consumed_complains_and_drops_package_if_return_route_id_is_unrecognized");
+        let save_params_arc = Arc::new(Mutex::new(vec![]));
+        let live_token = make_resumption_token(make_meaningless_stream_key(), now_time_t() + 30);
+        let expired_token = make_resumption_token(
+            StreamKey::new(
+                PublicKey::new(b"originator"),
+                SocketAddr::from_str("2.3.4.5:1111").unwrap(),
+            ),
+            now_time_t() - 1,
+        );
+        let store = ResumptionStoreMock::new()
+            .save_params(&save_params_arc)
+            .load_result(vec![live_token.clone(), expired_token]);
         let mut subject = ProxyServer::new(
-            cryptde,
+            main_cryptde(),
             alias_cryptde(),
             true,
             Some(STANDARD_CONSUMING_WALLET_BALANCE),
+            None,
         );
-        let stream_key = make_meaningless_stream_key();
-        subject
-            .keys_and_addrs
-            .insert(stream_key, SocketAddr::from_str("1.2.3.4:5678").unwrap());
-        let subject_addr: Addr<ProxyServer> = subject.start();
-        let mut peer_actors = peer_actors_builder()
-            .dispatcher(dispatcher)
-            .accountant(accountant)
-            .build();
-        peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
-        let client_response_payload = ClientResponsePayload_0v1 {
-            stream_key,
-            sequenced_packet: SequencedPacket {
-                data: b"some data".to_vec(),
-                sequence_number: 4321,
-                last_data: false,
-            },
-        };
-        let expired_cores_package = ExpiredCoresPackage::new(
-            SocketAddr::from_str("1.2.3.4:1234").unwrap(),
-            Some(make_wallet("irrelevant")),
-            return_route_with_id(cryptde, 1234),
-            client_response_payload,
-            0,
+
+        subject.load_resumption_tokens(Box::new(store));
+
+        assert_eq!(subject.resumption_tokens, vec![live_token]);
+        assert_eq!(*save_params_arc.lock().unwrap(), vec![vec![]]);
+        TestLogHandler::new().exists_log_containing(
+            "INFO: ProxyServer: 1 idle stream(s) from before the restart can be resumed",
         );
-        subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+    }
 
-        subject_addr.try_send(expired_cores_package).unwrap();
+    #[test]
+    fn checkpoint_saves_only_idle_streams_and_saves_nothing_twice() {
+        let exit_cryptde = CryptDENull::from(&PublicKey::new(b"exit"), DEFAULT_CHAIN_ID);
+        let idle_key = make_meaningless_stream_key();
+        let busy_key = StreamKey::new(
+            PublicKey::new(b"originator"),
+            SocketAddr::from_str("2.3.4.5:2222").unwrap(),
+        );
+        let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
+        let (mut subject, _) =
+            make_subject_awaiting_responses(&exit_cryptde, idle_key, socket_addr);
+        let route = subject.stream_key_routes.get(&idle_key).unwrap().clone();
+        subject.stream_key_routes.insert(busy_key, route);
+        let save_params_arc = Arc::new(Mutex::new(vec![]));
+        subject.resumption_store_opt = Some(Box::new(
+            ResumptionStoreMock::new().save_params(&save_params_arc),
+        ));
+        let now = Instant::now();
+        let mut idle = ResumableStream::new("example.com".to_string(), HTTP_PORT, now);
+        idle.next_request_sequence = 3;
+        subject.resumable_streams.insert(idle_key, idle);
+        subject
+            .receive_windows
+            .insert(idle_key, ReceiveWindow::starting_at(12));
+        let mut busy = ResumableStream::new("example.com".to_string(), HTTP_PORT, now);
+        busy.awaiting_response = true;
+        subject.resumable_streams.insert(busy_key, busy);
+        subject
+            .receive_windows
+            .insert(busy_key, ReceiveWindow::starting_at(12));
 
-        System::current().stop_with_code(0);
-        system.run();
-        TestLogHandler::new().exists_log_containing("ERROR: ProxyServer: Can't report services consumed: received response with bogus return-route ID 1234. Ignoring");
-        assert_eq!(dispatcher_recording_arc.lock().unwrap().len(), 0);
-        assert_eq!(accountant_recording_arc.lock().unwrap().len(), 0);
+        subject.checkpoint_resumable_streams(now);
+        subject.checkpoint_resumable_streams(now + RESUMPTION_QUIET_PERIOD);
+        subject
+            .resumable_streams
+            .get_mut(&idle_key)
+            .unwrap()
+            .awaiting_response = true;
+        subject.checkpoint_resumable_streams(now + RESUMPTION_QUIET_PERIOD);
+        subject.checkpoint_resumable_streams(now + RESUMPTION_QUIET_PERIOD);
+
+        let save_params = save_params_arc.lock().unwrap();
+        assert_eq!(save_params.len(), 2);
+        let token = &save_params[0][0];
+        assert_eq!(
+            *token,
+            ResumptionToken {
+                stream_key: idle_key,
+                exit_key: exit_cryptde.public_key().clone(),
+                hostname: "example.com".to_string(),
+                port: HTTP_PORT,
+                next_request_sequence: 3,
+                next_response_sequence: 12,
+                expires_at: token.expires_at,
+            }
+        );
+        assert_eq!(save_params[0].len(), 1);
+        assert_eq!(save_params[1], vec![]);
     }
 
     #[test]
-    fn report_response_services_consumed_complains_and_drops_package_if_return_route_id_is_unreadable(
-    ) {
+    fn a_new_connection_to_the_same_host_takes_over_a_resumable_stream() {
         init_test_logging();
-        let cryptde = main_cryptde();
-        let (dispatcher, _, dispatcher_recording_arc) = make_recorder();
-        let (accountant, _, accountant_recording_arc) = make_recorder();
-        let system = System::new("report_response_services_consumed_complains_and_drops_package_if_return_route_id_is_unreadable");
-        let mut subject = ProxyServer::new(
-            cryptde,
-            alias_cryptde(),
-            true,
-            Some(STANDARD_CONSUMING_WALLET_BALANCE),
+        let (hopper_mock, hopper_awaiter, hopper_recording_arc) = make_recorder();
+        let (neighborhood_mock, neighborhood_awaiter, neighborhood_recording_arc) = make_recorder();
+        let neighborhood_mock = neighborhood_mock.route_query_response(Some(
+            make_round_trip_route_query_response(vec![PublicKey::new(&[1]), PublicKey::new(&[2])]),
+        ));
+        let new_key = make_meaningless_stream_key();
+        let old_key = StreamKey::new(
+            PublicKey::new(b"originator"),
+            SocketAddr::from_str("2.3.4.5:3333").unwrap(),
+        );
+        let token = make_resumption_token(old_key, now_time_t() + 30);
+        thread::spawn(move || {
+            let system =
+                System::new("a_new_connection_to_the_same_host_takes_over_a_resumable_stream");
+            let mut subject = ProxyServer::new(
+                main_cryptde(),
+                alias_cryptde(),
+                true,
+                Some(STANDARD_CONSUMING_WALLET_BALANCE),
+                None,
+            );
+            subject.stream_key_factory = Box::new(StreamKeyFactoryMock::new().make_result(new_key));
+            subject.load_resumption_tokens(Box::new(
+                ResumptionStoreMock::new().load_result(vec![token]),
+            ));
+            let subject_addr: Addr<ProxyServer> = subject.start();
+            let mut peer_actors = peer_actors_builder()
+                .hopper(hopper_mock)
+                .neighborhood(neighborhood_mock)
+                .build();
+            peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
+            subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+            subject_addr
+                .try_send(InboundClientData {
+                    peer_addr: SocketAddr::from_str("1.2.3.4:5678").unwrap(),
+                    reception_port: Some(HTTP_PORT),
+                    sequence_number: Some(0),
+                    last_data: false,
+                    is_clandestine: false,
+                    data: b"GET /index.html HTTP/1.1\r\nHost: example.com\r\n\r\n".to_vec(),
+                })
+                .unwrap();
+
+            system.run();
+        });
+
+        hopper_awaiter.await_message_count(1);
+        neighborhood_awaiter.await_message_count(1);
+        let exit_cryptde = CryptDENull::from(&PublicKey::new(b"exit"), DEFAULT_CHAIN_ID);
+        let hopper_recording = hopper_recording_arc.lock().unwrap();
+        let record = hopper_recording.get_record::<IncipientCoresPackage>(0);
+        let payload = match decodex::<MessageType>(&exit_cryptde, &record.payload).unwrap() {
+            MessageType::ClientRequest(vd) => vd
+                .extract(&crate::sub_lib::migrations::client_request_payload::MIGRATIONS)
+                .unwrap(),
+            other => panic!("Wrong payload type: {:?}", other),
+        };
+        assert_eq!(payload.stream_key, old_key);
+        assert_eq!(payload.sequenced_packet.sequence_number, 3);
+        assert_eq!(payload.is_resumption, true);
+        let neighborhood_recording = neighborhood_recording_arc.lock().unwrap();
+        assert_eq!(
+            neighborhood_recording.get_record::<RouteQueryMessage>(0),
+            &RouteQueryMessage::data_indefinite_route_request(DEFAULT_MINIMUM_HOP_COUNT)
+                .with_exit_target(Some("example.com".to_string()), HTTP_PORT)
+                .with_target_key(PublicKey::new(b"exit"))
         );
+        TestLogHandler::new().exists_log_containing(&format!(
+            "INFO: ProxyServer: Resuming stream {} to example.com:80 from before the restart for 1.2.3.4:5678",
+            old_key
+        ));
+    }
+
+    #[test]
+    fn responses_on_a_resumed_stream_are_renumbered_for_the_new_connection() {
+        let system = System::new("test");
+        let exit_cryptde = CryptDENull::from(&PublicKey::new(b"exit"), DEFAULT_CHAIN_ID);
         let stream_key = make_meaningless_stream_key();
-        subject
-            .keys_and_addrs
-            .insert(stream_key, SocketAddr::from_str("1.2.3.4:5678").unwrap());
-        let subject_addr: Addr<ProxyServer> = subject.start();
-        let mut peer_actors = peer_actors_builder()
+        let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
+        let (mut subject, _) =
+            make_subject_awaiting_responses(&exit_cryptde, stream_key, socket_addr);
+        let (dispatcher, _, dispatcher_recording_arc) = make_recorder();
+        let (hopper, _, _) = make_recorder();
+        let peer_actors = peer_actors_builder()
             .dispatcher(dispatcher)
-            .accountant(accountant)
+            .hopper(hopper)
             .build();
-        peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
-        let client_response_payload = ClientResponsePayload_0v1 {
-            stream_key,
-            sequenced_packet: SequencedPacket {
-                data: b"some data".to_vec(),
-                sequence_number: 4321,
-                last_data: false,
-            },
-        };
-        let expired_cores_package = ExpiredCoresPackage::new(
-            SocketAddr::from_str("1.2.3.4:1234").unwrap(),
-            Some(make_wallet("irrelevant")),
-            Route {
-                hops: vec![make_cover_hop(cryptde), CryptData::new(&[0])],
-            },
-            client_response_payload,
-            0,
+        let mut subs = ProxyServerOutSubs::default();
+        subs.dispatcher = peer_actors.dispatcher.from_dispatcher_client;
+        subs.hopper = peer_actors.hopper.from_hopper_client;
+        subject.subs = Some(subs);
+        subject
+            .receive_windows
+            .insert(stream_key, ReceiveWindow::starting_at(12));
+        let mut stream = ResumableStream::new("example.com".to_string(), HTTP_PORT, Instant::now());
+        stream.response_offset = 12;
+        stream.awaiting_response = true;
+        stream.resumed_request_opt = Some(InboundClientData {
+            peer_addr: socket_addr,
+            reception_port: Some(HTTP_PORT),
+            sequence_number: Some(0),
+            last_data: false,
+            is_clandestine: false,
+            data: b"GET / HTTP/1.1\r\n\r\n".to_vec(),
+        });
+        subject.resumable_streams.insert(stream_key, stream);
+
+        subject.handle_client_response_payload(&make_response_package(stream_key, 11, false));
+        subject.handle_client_response_payload(&make_response_package(stream_key, 12, false));
+
+        let stream = subject.resumable_streams.get(&stream_key).unwrap();
+        assert_eq!(stream.awaiting_response, false);
+        assert_eq!(stream.resumed_request_opt.is_none(), true);
+        System::current().stop();
+        system.run();
+        let dispatcher_recording = dispatcher_recording_arc.lock().unwrap();
+        assert_eq!(
+            dispatcher_recording
+                .get_record::<TransmitDataMsg>(0)
+                .sequence_number,
+            Some(0)
         );
-        subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+        assert_eq!(dispatcher_recording.len(), 1);
+    }
 
-        subject_addr.try_send(expired_cores_package).unwrap();
+    #[test]
+    fn a_refused_resumption_starts_the_request_over_on_a_stream_of_its_own() {
+        init_test_logging();
+        let (hopper_mock, hopper_awaiter, hopper_recording_arc) = make_recorder();
+        let neighborhood_mock =
+            Recorder::new().route_query_response(Some(make_round_trip_route_query_response(vec![
+                PublicKey::new(&[1]),
+                PublicKey::new(&[2]),
+            ])));
+        let stream_key = make_meaningless_stream_key();
+        let socket_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
+        let request_data = b"GET /index.html HTTP/1.1\r\nHost: example.com\r\n\r\n".to_vec();
+        let request_data_inner = request_data.clone();
+        thread::spawn(move || {
+            let system =
+                System::new("a_refused_resumption_starts_the_request_over_on_a_stream_of_its_own");
+            let exit_cryptde = CryptDENull::from(&PublicKey::new(b"exit"), DEFAULT_CHAIN_ID);
+            let (mut subject, _) =
+                make_subject_awaiting_responses(&exit_cryptde, stream_key, socket_addr);
+            let mut stream =
+                ResumableStream::new("example.com".to_string(), HTTP_PORT, Instant::now());
+            stream.request_offset = 3;
+            stream.response_offset = 12;
+            stream.awaiting_response = true;
+            stream.resumed_request_opt = Some(InboundClientData {
+                peer_addr: socket_addr,
+                reception_port: Some(HTTP_PORT),
+                sequence_number: Some(0),
+                last_data: false,
+                is_clandestine: false,
+                data: request_data_inner,
+            });
+            subject.resumable_streams.insert(stream_key, stream);
+            let subject_addr: Addr<ProxyServer> = subject.start();
+            let mut peer_actors = peer_actors_builder()
+                .hopper(hopper_mock)
+                .neighborhood(neighborhood_mock)
+                .build();
+            peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
+            subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+            let mut refusal = make_response_package(stream_key, 0, true);
+            refusal.payload.sequenced_packet.data = vec![];
+            subject_addr.try_send(refusal).unwrap();
+
+            system.run();
+        });
 
-        System::current().stop_with_code(0);
-        system.run();
-        TestLogHandler::new().exists_log_containing(
-            "ERROR: ProxyServer: Can't report services consumed: DecryptionError(InvalidKey(\"Could not decrypt with",
-        );
-        assert_eq!(dispatcher_recording_arc.lock().unwrap().len(), 0);
-        assert_eq!(accountant_recording_arc.lock().unwrap().len(), 0);
+        hopper_awaiter.await_message_count(1);
+        let exit_cryptde = CryptDENull::from(&PublicKey::new(b"exit"), DEFAULT_CHAIN_ID);
+        let hopper_recording = hopper_recording_arc.lock().unwrap();
+        let record = hopper_recording.get_record::<IncipientCoresPackage>(0);
+        let payload = match decodex::<MessageType>(&exit_cryptde, &record.payload).unwrap() {
+            MessageType::ClientRequest(vd) => vd
+                .extract(&crate::sub_lib::migrations::client_request_payload::MIGRATIONS)
+                .unwrap(),
+            other => panic!("Wrong payload type: {:?}", other),
+        };
+        assert_eq!(payload.stream_key, stream_key.retried(1));
+        assert_eq!(payload.sequenced_packet.sequence_number, 0);
+        assert_eq!(payload.sequenced_packet.data, request_data);
+        assert_eq!(payload.is_resumption, false);
+        TestLogHandler::new().exists_log_containing(&format!(
+            "INFO: ProxyServer: Exit Node no longer had stream {}; starting the request from 1.2.3.4:5678 over as stream {}",
+            stream_key,
+            stream_key.retried(1)
+        ));
     }
 
     #[test]
-    fn return_route_ids_expire_when_instructed() {
-        init_test_logging();
-        let cryptde = main_cryptde();
+    fn streams_request_lists_consumed_streams_for_the_proxy_client_to_complete() {
+        let (hopper_mock, hopper_awaiter, _) = make_recorder();
+        let (proxy_client_mock, proxy_client_awaiter, proxy_client_recording_arc) = make_recorder();
+        let neighborhood_mock =
+            Recorder::new().route_query_response(Some(make_round_trip_route_query_response(vec![
+                PublicKey::new(&[1]),
+                PublicKey::new(&[2]),
+            ])));
         let stream_key = make_meaningless_stream_key();
-
+        let request_data = b"GET /index.html HTTP/1.1\r\nHost: example.com\r\n\r\n".to_vec();
+        let request_len = request_data.len() as u64;
         let (tx, rx) = mpsc::channel();
         thread::spawn(move || {
-            let system = System::new("report_response_services_consumed_complains_and_drops_package_if_return_route_id_does_not_exist");
+            let system = System::new(
+                "streams_request_lists_consumed_streams_for_the_proxy_client_to_complete",
+            );
             let mut subject = ProxyServer::new(
-                cryptde,
+                main_cryptde(),
                 alias_cryptde(),
                 true,
                 Some(STANDARD_CONSUMING_WALLET_BALANCE),
+                None,
             );
-            subject.route_ids_to_return_routes = TtlHashMap::new(Duration::from_millis(250));
-            subject
-                .keys_and_addrs
-                .insert(stream_key, SocketAddr::from_str("1.2.3.4:5678").unwrap());
-            subject.route_ids_to_return_routes.insert(
-                1234,
-                AddReturnRouteMessage {
-                    return_route_id: 1234,
-                    expected_services: vec![],
-                    protocol: ProxyProtocol::TLS,
-                    server_name: None,
-                },
-            );
+            subject.stream_key_factory =
+                Box::new(StreamKeyFactoryMock::new().make_result(stream_key));
             let subject_addr: Addr<ProxyServer> = subject.start();
-            let mut peer_actors = peer_actors_builder().build();
+            let mut peer_actors = peer_actors_builder()
+                .hopper(hopper_mock)
+                .neighborhood(neighborhood_mock)
+                .proxy_client(proxy_client_mock)
+                .build();
             peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
             subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+            subject_addr
+                .try_send(InboundClientData {
+                    peer_addr: SocketAddr::from_str("1.2.3.4:5678").unwrap(),
+                    reception_port: Some(HTTP_PORT),
+                    sequence_number: Some(0),
+                    last_data: false,
+                    is_clandestine: false,
+                    data: request_data,
+                })
+                .unwrap();
             tx.send(subject_addr).unwrap();
 
             system.run();
         });
-
         let subject_addr = rx.recv().unwrap();
-
-        thread::sleep(Duration::from_millis(300));
-
-        let client_response_payload = ClientResponsePayload_0v1 {
-            stream_key,
-            sequenced_packet: SequencedPacket {
-                data: b"some data".to_vec(),
-                sequence_number: 4321,
-                last_data: false,
-            },
-        };
-        let expired_cores_package = ExpiredCoresPackage::new(
-            SocketAddr::from_str("1.2.3.4:1234").unwrap(),
-            Some(make_wallet("irrelevant")),
-            return_route_with_id(cryptde, 1234),
-            client_response_payload,
-            0,
-        );
-        subject_addr.try_send(expired_cores_package).unwrap();
-
-        TestLogHandler::new().await_log_containing("ERROR: ProxyServer: Can't report services consumed: received response with bogus return-route ID 1234. Ignoring", 1000);
-    }
-
-    #[test]
-    fn handle_stream_shutdown_msg_handles_unknown_peer_addr() {
-        let mut subject = ProxyServer::new(main_cryptde(), alias_cryptde(), true, None);
-        let unaffected_socket_addr = SocketAddr::from_str("2.3.4.5:6789").unwrap();
-        let unaffected_stream_key =
-            StreamKey::new(main_cryptde().public_key().clone(), unaffected_socket_addr);
-        subject
-            .keys_and_addrs
-            .insert(unaffected_stream_key, unaffected_socket_addr);
-        subject.stream_key_routes.insert(
-            unaffected_stream_key,
-            RouteQueryResponse {
-                route: Route { hops: vec![] },
-                expected_services: ExpectedServices::RoundTrip(vec![], vec![], 1234),
-            },
-        );
-        subject
-            .tunneled_hosts
-            .insert(unaffected_stream_key, "blah".to_string());
-
-        subject.handle_stream_shutdown_msg(StreamShutdownMsg {
-            peer_addr: SocketAddr::from_str("1.2.3.4:5678").unwrap(),
-            stream_type: RemovedStreamType::NonClandestine(NonClandestineAttributes {
-                reception_port: HTTP_PORT,
-                sequence_number: 1234,
-            }),
-            report_to_counterpart: true,
-        });
-
-        // Subject is unbound but didn't panic; therefore, no attempt to send to Hopper: perfect!
-        assert!(subject
-            .keys_and_addrs
-            .a_to_b(&unaffected_stream_key)
-            .is_some());
-        assert!(subject
-            .stream_key_routes
-            .contains_key(&unaffected_stream_key));
-        assert!(subject.tunneled_hosts.contains_key(&unaffected_stream_key));
-    }
-
-    #[test]
-    fn handle_stream_shutdown_msg_reports_to_counterpart_through_tunnel_when_necessary() {
-        let system = System::new("test");
-        let mut subject = ProxyServer::new(
-            main_cryptde(),
-            alias_cryptde(),
-            true,
-            Some(STANDARD_CONSUMING_WALLET_BALANCE),
-        );
-        let unaffected_socket_addr = SocketAddr::from_str("2.3.4.5:6789").unwrap();
-        let unaffected_stream_key =
-            StreamKey::new(main_cryptde().public_key().clone(), unaffected_socket_addr);
-        let affected_socket_addr = SocketAddr::from_str("3.4.5.6:7890").unwrap();
-        let affected_stream_key =
-            StreamKey::new(main_cryptde().public_key().clone(), affected_socket_addr);
-        let affected_cryptde = CryptDENull::from(&PublicKey::new(b"affected"), DEFAULT_CHAIN_ID);
-        subject
-            .keys_and_addrs
-            .insert(unaffected_stream_key, unaffected_socket_addr);
-        subject
-            .keys_and_addrs
-            .insert(affected_stream_key, affected_socket_addr);
-        subject.stream_key_routes.insert(
-            unaffected_stream_key,
-            RouteQueryResponse {
-                route: Route { hops: vec![] },
-                expected_services: ExpectedServices::RoundTrip(vec![], vec![], 1234),
-            },
-        );
-        let affected_route = Route::round_trip(
-            RouteSegment::new(
-                vec![main_cryptde().public_key(), affected_cryptde.public_key()],
-                Component::ProxyClient,
-            ),
-            RouteSegment::new(
-                vec![affected_cryptde.public_key(), main_cryptde().public_key()],
-                Component::ProxyServer,
-            ),
-            main_cryptde(),
-            Some(make_paying_wallet(b"consuming")),
-            1234,
-            Some(contract_address(DEFAULT_CHAIN_ID)),
-        )
-        .unwrap();
-        let affected_expected_services = vec![ExpectedService::Exit(
-            affected_cryptde.public_key().clone(),
-            make_paying_wallet(b"1234"),
-            DEFAULT_RATE_PACK,
-        )];
-        subject.stream_key_routes.insert(
-            affected_stream_key,
-            RouteQueryResponse {
-                route: affected_route.clone(),
-                expected_services: ExpectedServices::RoundTrip(
-                    affected_expected_services,
-                    vec![],
-                    1234,
-                ),
-            },
-        );
-        subject
-            .tunneled_hosts
-            .insert(unaffected_stream_key, "blah".to_string());
-        subject
-            .tunneled_hosts
-            .insert(affected_stream_key, "tunneled.com".to_string());
-        let subject_addr = subject.start();
-        let (hopper, _, hopper_recording_arc) = make_recorder();
-        let (proxy_server, _, proxy_server_recording_arc) = make_recorder();
-        let peer_actors = peer_actors_builder()
-            .hopper(hopper)
-            .proxy_server(proxy_server)
-            .build();
-        subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+        hopper_awaiter.await_message_count(1);
 
         subject_addr
-            .try_send(StreamShutdownMsg {
-                peer_addr: affected_socket_addr,
-                stream_type: RemovedStreamType::NonClandestine(NonClandestineAttributes {
-                    reception_port: TLS_PORT,
-                    sequence_number: 1234,
-                }),
-                report_to_counterpart: true,
+            .try_send(NodeFromUiMessage {
+                client_id: 1234,
+                body: UiStreamsRequest {}.tmb(4321),
             })
             .unwrap();
 
-        System::current().stop_with_code(0);
-        system.run();
-        let recording = hopper_recording_arc.lock().unwrap();
-        let record = recording.get_record::<IncipientCoresPackage>(0);
-        assert_eq!(record.route, affected_route);
-        let payload = decodex::<MessageType>(&affected_cryptde, &record.payload).unwrap();
-        match payload {
-            MessageType::ClientRequest(vd) => assert_eq!(
-                vd.extract(&crate::sub_lib::migrations::client_request_payload::MIGRATIONS)
-                    .unwrap(),
-                ClientRequestPayload_0v1 {
-                    stream_key: affected_stream_key,
-                    sequenced_packet: SequencedPacket::new(vec![], 1234, true),
-                    target_hostname: Some(String::from("tunneled.com")),
-                    target_port: 443,
-                    protocol: ProxyProtocol::TLS,
-                    originator_public_key: alias_cryptde().public_key().clone(),
-                }
-            ),
-            other => panic!("Wrong payload type: {:?}", other),
-        }
-        let recording = proxy_server_recording_arc.lock().unwrap();
-        let record = recording.get_record::<StreamShutdownMsg>(recording.len() - 1);
+        proxy_client_awaiter.await_message_count(1);
+        let proxy_client_recording = proxy_client_recording_arc.lock().unwrap();
         assert_eq!(
-            record,
-            &StreamShutdownMsg {
-                peer_addr: affected_socket_addr,
-                stream_type: RemovedStreamType::NonClandestine(NonClandestineAttributes {
-                    reception_port: 0,
-                    sequence_number: 0
-                }),
-                report_to_counterpart: false
+            proxy_client_recording.get_record::<StreamsQuery>(0),
+            &StreamsQuery {
+                client_id: 1234,
+                context_id: 4321,
+                consuming: vec![UiStream {
+                    stream_key: stream_key.to_string(),
+                    protocol: "HTTP".to_string(),
+                    target_host_opt: Some("example.com".to_string()),
+                    bytes_out: request_len,
+                    bytes_in: 0,
+                    hop_count_opt: Some(3),
+                    age_seconds: 0,
+                }],
             }
         );
     }
 
-    #[test]
-    fn handle_stream_shutdown_msg_reports_to_counterpart_without_tunnel_when_necessary() {
-        let system = System::new("test");
-        let mut subject = ProxyServer::new(
-            main_cryptde(),
-            alias_cryptde(),
-            true,
-            Some(STANDARD_CONSUMING_WALLET_BALANCE),
-        );
-        let unaffected_socket_addr = SocketAddr::from_str("2.3.4.5:6789").unwrap();
-        let unaffected_stream_key =
-            StreamKey::new(main_cryptde().public_key().clone(), unaffected_socket_addr);
-        let affected_socket_addr = SocketAddr::from_str("3.4.5.6:7890").unwrap();
-        let affected_stream_key =
-            StreamKey::new(main_cryptde().public_key().clone(), affected_socket_addr);
-        let affected_cryptde = CryptDENull::from(&PublicKey::new(b"affected"), DEFAULT_CHAIN_ID);
-        subject
-            .keys_and_addrs
-            .insert(unaffected_stream_key, unaffected_socket_addr);
-        subject
-            .keys_and_addrs
-            .insert(affected_stream_key, affected_socket_addr);
-        subject.stream_key_routes.insert(
-            unaffected_stream_key,
-            RouteQueryResponse {
-                route: Route { hops: vec![] },
-                expected_services: ExpectedServices::RoundTrip(vec![], vec![], 1234),
-            },
-        );
-        let affected_route = Route::round_trip(
-            RouteSegment::new(
-                vec![main_cryptde().public_key(), affected_cryptde.public_key()],
-                Component::ProxyClient,
-            ),
-            RouteSegment::new(
-                vec![affected_cryptde.public_key(), main_cryptde().public_key()],
-                Component::ProxyServer,
-            ),
+    fn make_hidden_server_name_client_hello(peer_addr: &str) -> InboundClientData {
+        #[rustfmt::skip]
+        let data = vec![
+            0x16, // content_type: Handshake
+            0x00, 0x00, 0x00, 0x00, // version, length: don't care
+            0x01, // handshake_type: ClientHello
+            0x00, 0x00, 0x00, 0x00, 0x00, // length, version: don't care
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, // random: don't care
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, // random: don't care
+            0x00, // session_id_length
+            0x00, 0x00, // cipher_suites_length
+            0x00, // compression_methods_length
+            0x00, 0x06, // extensions_length
+            0xFF, 0xCE, // extension_type: encrypted_server_name
+            0x00, 0x02, // extension_length
+            0x01, 0x02, // encrypted server name
+        ];
+        InboundClientData {
+            peer_addr: SocketAddr::from_str(peer_addr).unwrap(),
+            reception_port: Some(TLS_PORT),
+            sequence_number: Some(0),
+            last_data: false,
+            is_clandestine: false,
+            data,
+        }
+    }
+
+    #[test]
+    fn client_hello_with_hidden_server_name_is_refused_by_default() {
+        let system = System::new("client_hello_with_hidden_server_name_is_refused_by_default");
+        let (dispatcher, _, dispatcher_recording_arc) = make_recorder();
+        let (neighborhood, _, neighborhood_recording_arc) = make_recorder();
+        let subject = ProxyServer::new(
             main_cryptde(),
-            Some(make_paying_wallet(b"consuming")),
-            1234,
-            Some(contract_address(DEFAULT_CHAIN_ID)),
-        )
-        .unwrap();
-        let affected_expected_services = vec![ExpectedService::Exit(
-            affected_cryptde.public_key().clone(),
-            make_paying_wallet(b"1234"),
-            DEFAULT_RATE_PACK,
-        )];
-        subject.stream_key_routes.insert(
-            affected_stream_key,
-            RouteQueryResponse {
-                route: affected_route.clone(),
-                expected_services: ExpectedServices::RoundTrip(
-                    affected_expected_services,
-                    vec![],
-                    1234,
-                ),
-            },
+            alias_cryptde(),
+            true,
+            Some(STANDARD_CONSUMING_WALLET_BALANCE),
+            None,
         );
-        let subject_addr = subject.start();
-        let (hopper, _, hopper_recording_arc) = make_recorder();
-        let (proxy_server, _, proxy_server_recording_arc) = make_recorder();
-        let peer_actors = peer_actors_builder()
-            .hopper(hopper)
-            .proxy_server(proxy_server)
+        let subject_addr: Addr<ProxyServer> = subject.start();
+        let mut peer_actors = peer_actors_builder()
+            .dispatcher(dispatcher)
+            .neighborhood(neighborhood)
             .build();
+        peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
         subject_addr.try_send(BindMessage { peer_actors }).unwrap();
 
         subject_addr
-            .try_send(StreamShutdownMsg {
-                peer_addr: affected_socket_addr,
-                stream_type: RemovedStreamType::NonClandestine(NonClandestineAttributes {
-                    reception_port: HTTP_PORT,
-                    sequence_number: 1234,
-                }),
-                report_to_counterpart: true,
-            })
+            .try_send(make_hidden_server_name_client_hello("1.2.3.4:5678"))
             .unwrap();
 
-        System::current().stop_with_code(0);
+        System::current().stop();
         system.run();
-        let recording = hopper_recording_arc.lock().unwrap();
-        let record = recording.get_record::<IncipientCoresPackage>(0);
-        assert_eq!(record.route, affected_route);
-        let payload = decodex::<MessageType>(&affected_cryptde, &record.payload).unwrap();
-        match payload {
-            MessageType::ClientRequest(vd) => assert_eq!(
-                vd.extract(&crate::sub_lib::migrations::client_request_payload::MIGRATIONS)
-                    .unwrap(),
-                ClientRequestPayload_0v1 {
-                    stream_key: affected_stream_key,
-                    sequenced_packet: SequencedPacket::new(vec![], 1234, true),
-                    target_hostname: None,
-                    target_port: HTTP_PORT,
-                    protocol: ProxyProtocol::HTTP,
-                    originator_public_key: alias_cryptde().public_key().clone(),
-                }
-            ),
-            other => panic!("Wrong payload type: {:?}", other),
-        }
-        let recording = proxy_server_recording_arc.lock().unwrap();
-        let record = recording.get_record::<StreamShutdownMsg>(recording.len() - 1);
+        let dispatcher_recording = dispatcher_recording_arc.lock().unwrap();
         assert_eq!(
-            record,
-            &StreamShutdownMsg {
-                peer_addr: affected_socket_addr,
-                stream_type: RemovedStreamType::NonClandestine(NonClandestineAttributes {
-                    reception_port: 0,
-                    sequence_number: 0
-                }),
-                report_to_counterpart: false
+            dispatcher_recording.get_record::<TransmitDataMsg>(0),
+            &TransmitDataMsg {
+                endpoint: Endpoint::Socket(SocketAddr::from_str("1.2.3.4:5678").unwrap()),
+                last_data: true,
+                sequence_number: Some(0),
+                data: ServerImpersonatorTls {}
+                    .dns_resolution_failure_response(main_cryptde().public_key(), None),
+                relayed_from_opt: None,
             }
         );
+        assert_eq!(neighborhood_recording_arc.lock().unwrap().len(), 0);
     }
 
     #[test]
-    fn handle_stream_shutdown_msg_does_not_report_to_counterpart_when_unnecessary() {
-        let mut subject = ProxyServer::new(main_cryptde(), alias_cryptde(), true, None);
-        let unaffected_socket_addr = SocketAddr::from_str("2.3.4.5:6789").unwrap();
-        let unaffected_stream_key =
-            StreamKey::new(main_cryptde().public_key().clone(), unaffected_socket_addr);
-        let affected_socket_addr = SocketAddr::from_str("3.4.5.6:7890").unwrap();
-        let affected_stream_key =
-            StreamKey::new(main_cryptde().public_key().clone(), affected_socket_addr);
-        subject
-            .keys_and_addrs
-            .insert(unaffected_stream_key, unaffected_socket_addr);
-        subject
-            .keys_and_addrs
-            .insert(affected_stream_key, affected_socket_addr);
-        subject.stream_key_routes.insert(
-            unaffected_stream_key,
-            RouteQueryResponse {
-                route: Route { hops: vec![] },
-                expected_services: ExpectedServices::RoundTrip(vec![], vec![], 1234),
-            },
-        );
-        subject.stream_key_routes.insert(
-            affected_stream_key,
-            RouteQueryResponse {
-                route: Route { hops: vec![] },
-                expected_services: ExpectedServices::RoundTrip(vec![], vec![], 1234),
-            },
-        );
-        subject
-            .tunneled_hosts
-            .insert(unaffected_stream_key, "blah".to_string());
-        subject
-            .tunneled_hosts
-            .insert(affected_stream_key, "blah".to_string());
+    fn client_hello_with_hidden_server_name_goes_to_fallback_host() {
+        let (neighborhood_mock, neighborhood_awaiter, neighborhood_recording_arc) = make_recorder();
+        let neighborhood_mock = neighborhood_mock.route_query_response(None);
 
-        subject.handle_stream_shutdown_msg(StreamShutdownMsg {
-            peer_addr: affected_socket_addr,
-            stream_type: RemovedStreamType::NonClandestine(NonClandestineAttributes {
-                reception_port: HTTP_PORT,
-                sequence_number: 1234,
-            }),
-            report_to_counterpart: false,
+        thread::spawn(move || {
+            let system = System::new("client_hello_with_hidden_server_name_goes_to_fallback_host");
+            let mut subject = ProxyServer::new(
+                main_cryptde(),
+                alias_cryptde(),
+                true,
+                Some(STANDARD_CONSUMING_WALLET_BALANCE),
+                None,
+            );
+            subject.set_encrypted_sni_fallback(EncryptedSniFallback::Host(
+                "front.example.com".to_string(),
+            ));
+            let subject_addr: Addr<ProxyServer> = subject.start();
+            let mut peer_actors = peer_actors_builder()
+                .neighborhood(neighborhood_mock)
+                .build();
+            peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
+            subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+
+            subject_addr
+                .try_send(make_hidden_server_name_client_hello("1.2.3.4:5678"))
+                .unwrap();
+
+            system.run();
         });
 
-        // Subject is unbound but didn't panic; therefore, no attempt to send to Hopper: perfect!
-        assert!(subject
-            .keys_and_addrs
-            .a_to_b(&unaffected_stream_key)
-            .is_some());
-        assert!(subject
-            .stream_key_routes
-            .contains_key(&unaffected_stream_key));
-        assert!(subject.tunneled_hosts.contains_key(&unaffected_stream_key));
-        assert!(subject
-            .keys_and_addrs
-            .a_to_b(&affected_stream_key)
-            .is_none());
-        assert!(!subject.stream_key_routes.contains_key(&affected_stream_key));
-        assert!(!subject.tunneled_hosts.contains_key(&affected_stream_key));
+        neighborhood_awaiter.await_message_count(1);
+        let neighborhood_recording = neighborhood_recording_arc.lock().unwrap();
+        assert_eq!(
+            neighborhood_recording.get_record::<RouteQueryMessage>(0),
+            &RouteQueryMessage::data_indefinite_route_request(DEFAULT_MINIMUM_HOP_COUNT)
+                .with_exit_target(Some("front.example.com".to_string()), TLS_PORT)
+        );
     }
 
     #[test]
-    #[should_panic(
-        expected = "ProxyServer should never get ShutdownStreamMsg about clandestine stream"
-    )]
-    fn handle_stream_shutdown_complains_about_clandestine_message() {
-        let system = System::new("test");
-        let subject = ProxyServer::new(main_cryptde(), alias_cryptde(), true, None);
-        let subject_addr = subject.start();
+    fn client_hello_with_hidden_server_name_waits_for_ui_to_name_the_server() {
+        let (neighborhood_mock, neighborhood_awaiter, neighborhood_recording_arc) = make_recorder();
+        let neighborhood_mock = neighborhood_mock.route_query_response(None);
+        let (ui_gateway, ui_gateway_awaiter, ui_gateway_recording_arc) = make_recorder();
+        let (dispatcher, dispatcher_awaiter, dispatcher_recording_arc) = make_recorder();
 
-        subject_addr
-            .try_send(StreamShutdownMsg {
-                peer_addr: SocketAddr::from_str("1.2.3.4:5678").unwrap(),
-                stream_type: RemovedStreamType::Clandestine,
-                report_to_counterpart: false,
+        thread::spawn(move || {
+            let system =
+                System::new("client_hello_with_hidden_server_name_waits_for_ui_to_name_the_server");
+            let mut subject = ProxyServer::new(
+                main_cryptde(),
+                alias_cryptde(),
+                true,
+                Some(STANDARD_CONSUMING_WALLET_BALANCE),
+                None,
+            );
+            subject.set_encrypted_sni_fallback(EncryptedSniFallback::Prompt);
+            let subject_addr: Addr<ProxyServer> = subject.start();
+            let mut peer_actors = peer_actors_builder()
+                .neighborhood(neighborhood_mock)
+                .ui_gateway(ui_gateway)
+                .dispatcher(dispatcher)
+                .build();
+            peer_actors.proxy_server = ProxyServer::make_subs_from(&subject_addr);
+            subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+
+            subject_addr
+                .try_send(make_hidden_server_name_client_hello("1.2.3.4:5678"))
+                .unwrap();
+            subject_addr
+                .try_send(make_hidden_server_name_client_hello("1.2.3.4:5679"))
+                .unwrap();
+            vec![
+                UiEncryptedSniHostRequest {
+                    connection_id: 3,
+                    host_name_opt: Some("real.example.com".to_string()),
+                },
+                UiEncryptedSniHostRequest {
+                    connection_id: 1,
+                    host_name_opt: Some("real.example.com:443".to_string()),
+                },
+                UiEncryptedSniHostRequest {
+                    connection_id: 1,
+                    host_name_opt: Some("real.example.com".to_string()),
+                },
+                UiEncryptedSniHostRequest {
+                    connection_id: 2,
+                    host_name_opt: None,
+                },
+            ]
+            .into_iter()
+            .enumerate()
+            .for_each(|(index, request)| {
+                subject_addr
+                    .try_send(NodeFromUiMessage {
+                        client_id: 1234,
+                        body: request.tmb(4321 + index as u64),
+                    })
+                    .unwrap()
+            });
+
+            system.run();
+        });
+
+        neighborhood_awaiter.await_message_count(1);
+        ui_gateway_awaiter.await_message_count(6);
+        dispatcher_awaiter.await_message_count(2);
+        let ui_gateway_recording = ui_gateway_recording_arc.lock().unwrap();
+        assert_eq!(
+            ui_gateway_recording.get_record::<NodeToUiMessage>(0),
+            &NodeToUiMessage {
+                target: AllClients,
+                body: UiEncryptedSniBroadcast {
+                    connection_id: 1,
+                    client_address: "1.2.3.4:5678".to_string(),
+                }
+                .tmb(0),
+            }
+        );
+        assert_eq!(
+            ui_gateway_recording
+                .get_record::<NodeToUiMessage>(1)
+                .body
+                .payload,
+            UiEncryptedSniBroadcast {
+                connection_id: 2,
+                client_address: "1.2.3.4:5679".to_string(),
+            }
+            .tmb(0)
+            .payload
+        );
+        assert_eq!(
+            ui_gateway_recording.get_record::<NodeToUiMessage>(2),
+            &NodeToUiMessage {
+                target: ClientId(1234),
+                body: MessageBody {
+                    opcode: "encryptedSniHost".to_string(),
+                    path: MessagePath::Conversation(4321),
+                    payload: Err((
+                        ENCRYPTED_SNI_ERROR,
+                        "No TLS connection 3 is waiting for a server".to_string()
+                    )),
+                },
+            }
+        );
+        assert_eq!(
+            ui_gateway_recording
+                .get_record::<NodeToUiMessage>(3)
+                .body
+                .payload,
+            Err((
+                ENCRYPTED_SNI_ERROR,
+                "'real.example.com:443' is not a host name".to_string()
+            ))
+        );
+        assert_eq!(
+            ui_gateway_recording.get_record::<NodeToUiMessage>(4).body,
+            UiEncryptedSniHostResponse {}.tmb(4323)
+        );
+        assert_eq!(
+            ui_gateway_recording.get_record::<NodeToUiMessage>(5).body,
+            UiEncryptedSniHostResponse {}.tmb(4324)
+        );
+        let neighborhood_recording = neighborhood_recording_arc.lock().unwrap();
+        assert_eq!(
+            neighborhood_recording.get_record::<RouteQueryMessage>(0),
+            &RouteQueryMessage::data_indefinite_route_request(DEFAULT_MINIMUM_HOP_COUNT)
+                .with_exit_target(Some("real.example.com".to_string()), TLS_PORT)
+        );
+        let dispatcher_recording = dispatcher_recording_arc.lock().unwrap();
+        let refusals = (0..2)
+            .map(|index| {
+                dispatcher_recording
+                    .get_record::<TransmitDataMsg>(index)
+                    .endpoint
+                    .clone()
             })
-            .unwrap();
+            .collect::<Vec<Endpoint>>();
+        // One for connection 2, refused by the UI; one for connection 1, which found no route
+        assert!(refusals.contains(&Endpoint::Socket(
+            SocketAddr::from_str("1.2.3.4:5679").unwrap()
+        )));
+        assert!(refusals.contains(&Endpoint::Socket(
+            SocketAddr::from_str("1.2.3.4:5678").unwrap()
+        )));
+        assert_eq!(neighborhood_recording.len(), 1);
+    }
 
-        System::current().stop_with_code(0);
-        system.run();
+    #[test]
+    fn only_the_first_packets_of_unrecognized_streams_are_sniffed() {
+        let subject = ProxyServer::new(
+            main_cryptde(),
+            alias_cryptde(),
+            true,
+            Some(STANDARD_CONSUMING_WALLET_BALANCE),
+            None,
+        );
+        let stream_key = make_meaningless_stream_key();
+        let packet = |port: u16, sequence_number: u64, data: &[u8]| InboundClientData {
+            peer_addr: SocketAddr::from_str(&format!("2.7.1.8:{}", port)).unwrap(),
+            reception_port: Some(HTTP_PORT),
+            sequence_number: Some(sequence_number),
+            last_data: false,
+            is_clandestine: false,
+            data: data.to_vec(),
+        };
+        let sniffed_ports = || {
+            SNIFFED_STREAMS
+                .samples()
+                .into_iter()
+                .filter(|sample| sample.client_addr.ip().to_string() == "2.7.1.8")
+                .map(|sample| sample.client_addr.port())
+                .collect::<Vec<u16>>()
+        };
+
+        subject.sniff_if_unrecognized(&packet(1, 0, b"\x00\x01garbage"), &stream_key);
+        subject.sniff_if_unrecognized(&packet(2, 1, b"\x00\x01garbage"), &stream_key);
+        subject.sniff_if_unrecognized(
+            &packet(3, 0, b"GET / HTTP/1.1\r\nHost: nowhere.com\r\n\r\n"),
+            &stream_key,
+        );
+
+        assert_eq!(sniffed_ports(), vec![1]);
     }
 }
@@ -0,0 +1,232 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use std::net::SocketAddr;
+#[cfg(target_os = "linux")]
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+#[cfg(target_os = "linux")]
+use std::path::Path;
+
+// Finds out which program on this computer opened a client connection, so that per-application
+// rules can apply to it. Connections from other computers never have an application.
+pub trait ProcessIdentifier: Send {
+    fn application(&self, client_addr: SocketAddr) -> Option<String>;
+}
+
+pub struct ProcessIdentifierReal {}
+
+impl ProcessIdentifier for ProcessIdentifierReal {
+    // The client's end of the connection is a socket whose local address is the client address;
+    // the program is whichever process holds a descriptor for that socket. Without root, only
+    // the processes of the Node's own user can be seen.
+    #[cfg(target_os = "linux")]
+    fn application(&self, client_addr: SocketAddr) -> Option<String> {
+        if !client_addr.ip().is_loopback() {
+            return None;
+        }
+        let inode = ["/proc/net/tcp", "/proc/net/tcp6"]
+            .iter()
+            .filter_map(|path| std::fs::read_to_string(path).ok())
+            .find_map(|table| socket_inode(&table, client_addr))?;
+        process_holding_socket(Path::new("/proc"), inode).map(|(_, name)| name)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn application(&self, _client_addr: SocketAddr) -> Option<String> {
+        None
+    }
+}
+
+// Names the program listening on a TCP port of this computer, and its process ID. Without root,
+// only the processes of the Node's own user can be seen.
+#[cfg(target_os = "linux")]
+pub fn port_holder(port: u16) -> Option<String> {
+    let inode = ["/proc/net/tcp", "/proc/net/tcp6"]
+        .iter()
+        .filter_map(|path| std::fs::read_to_string(path).ok())
+        .find_map(|table| listening_socket_inode(&table, port))?;
+    process_holding_socket(Path::new("/proc"), inode)
+        .map(|(process_id, name)| format!("{} (process {})", name, process_id))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn port_holder(_port: u16) -> Option<String> {
+    None
+}
+
+#[cfg(target_os = "linux")]
+fn socket_inode(table: &str, local_addr: SocketAddr) -> Option<u64> {
+    table.lines().skip(1).find_map(|line| {
+        let fields = line.split_whitespace().collect::<Vec<&str>>();
+        if fields.len() < 10 || parse_socket_addr(fields[1]) != Some(local_addr) {
+            return None;
+        }
+        fields[9].parse::<u64>().ok()
+    })
+}
+
+// 0A is the kernel's code for a socket in the LISTEN state
+#[cfg(target_os = "linux")]
+fn listening_socket_inode(table: &str, port: u16) -> Option<u64> {
+    table.lines().skip(1).find_map(|line| {
+        let fields = line.split_whitespace().collect::<Vec<&str>>();
+        if fields.len() < 10
+            || fields[3] != "0A"
+            || parse_socket_addr(fields[1]).map(|addr| addr.port()) != Some(port)
+        {
+            return None;
+        }
+        fields[9].parse::<u64>().ok()
+    })
+}
+
+// The kernel writes addresses as hex 32-bit words in host byte order, and ports in hex
+#[cfg(target_os = "linux")]
+fn parse_socket_addr(field: &str) -> Option<SocketAddr> {
+    let mut pieces = field.split(':');
+    let address_hex = pieces.next()?;
+    let port = u16::from_str_radix(pieces.next()?, 16).ok()?;
+    let mut octets = vec![];
+    for index in (0..address_hex.len()).step_by(8) {
+        let word = u32::from_str_radix(address_hex.get(index..(index + 8))?, 16).ok()?;
+        octets.extend_from_slice(&word.to_ne_bytes());
+    }
+    let ip = match octets.len() {
+        4 => IpAddr::V4(Ipv4Addr::new(octets[0], octets[1], octets[2], octets[3])),
+        16 => {
+            let mut bytes = [0u8; 16];
+            bytes.copy_from_slice(&octets);
+            let ipv6 = Ipv6Addr::from(bytes);
+            match ipv6.to_ipv4() {
+                Some(ipv4) if ipv6.segments()[5] == 0xFFFF => IpAddr::V4(ipv4),
+                _ => IpAddr::V6(ipv6),
+            }
+        }
+        _ => return None,
+    };
+    Some(SocketAddr::new(ip, port))
+}
+
+#[cfg(target_os = "linux")]
+fn process_holding_socket(proc_dir: &Path, inode: u64) -> Option<(u32, String)> {
+    let target = format!("socket:[{}]", inode);
+    std::fs::read_dir(proc_dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            entry
+                .file_name()
+                .to_str()
+                .map(|name| name.chars().all(|c| c.is_ascii_digit()))
+                .unwrap_or(false)
+        })
+        .find_map(|process_dir| {
+            let holds_socket = std::fs::read_dir(process_dir.path().join("fd"))
+                .ok()?
+                .filter_map(|entry| entry.ok())
+                .any(|fd| {
+                    std::fs::read_link(fd.path())
+                        .map(|link| link.to_string_lossy() == target)
+                        .unwrap_or(false)
+                });
+            if holds_socket {
+                let process_id = process_dir.file_name().to_str()?.parse::<u32>().ok()?;
+                std::fs::read_to_string(process_dir.path().join("comm"))
+                    .ok()
+                    .map(|comm| (process_id, comm.trim().to_string()))
+            } else {
+                None
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn parses_kernel_socket_addresses() {
+        assert_eq!(
+            parse_socket_addr("0100007F:DF4E"),
+            Some(SocketAddr::from_str("127.0.0.1:57166").unwrap())
+        );
+        assert_eq!(
+            parse_socket_addr("00000000000000000000000001000000:1F90"),
+            Some(SocketAddr::from_str("[::1]:8080").unwrap())
+        );
+        assert_eq!(
+            parse_socket_addr("0000000000000000FFFF00000100007F:1F90"),
+            Some(SocketAddr::from_str("127.0.0.1:8080").unwrap())
+        );
+        assert_eq!(parse_socket_addr("0100007F"), None);
+        assert_eq!(parse_socket_addr("booga:1F90"), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn finds_inode_of_socket_with_local_address() {
+        let table = "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode\n\
+            \x20  0: 0100007F:BC8F 00000000:0000 0A 00000000:00000000 00:00000000 00000000 65534        0 1049 1 000000007840761f 100 0 0 10 0\n\
+            \x20  1: 0100007F:DF4E 0100007F:BC8F 01 00000000:00000000 02:00000F1F 00000000     0        0 862283 2 00000000178044a2 20 4 2 14 8\n";
+
+        assert_eq!(
+            socket_inode(table, SocketAddr::from_str("127.0.0.1:57166").unwrap()),
+            Some(862283)
+        );
+        assert_eq!(
+            socket_inode(table, SocketAddr::from_str("127.0.0.1:1234").unwrap()),
+            None
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn identifies_this_process_as_the_application_behind_its_own_connection() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = std::net::TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let _server = listener.accept().unwrap();
+        let expected = std::fs::read_to_string("/proc/self/comm").unwrap();
+
+        let result = ProcessIdentifierReal {}.application(client.local_addr().unwrap());
+
+        assert_eq!(result, Some(expected.trim().to_string()));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn finds_inode_of_socket_listening_on_port() {
+        let table = "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode\n\
+            \x20  0: 0100007F:BC8F 00000000:0000 0A 00000000:00000000 00:00000000 00000000 65534        0 1049 1 000000007840761f 100 0 0 10 0\n\
+            \x20  1: 0100007F:DF4E 0100007F:BC8F 01 00000000:00000000 02:00000F1F 00000000     0        0 862283 2 00000000178044a2 20 4 2 14 8\n";
+
+        assert_eq!(listening_socket_inode(table, 48271), Some(1049));
+        assert_eq!(listening_socket_inode(table, 57166), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn identifies_this_process_as_the_holder_of_its_own_listening_port() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let expected = std::fs::read_to_string("/proc/self/comm").unwrap();
+
+        let result = port_holder(port);
+
+        assert_eq!(
+            result,
+            Some(format!(
+                "{} (process {})",
+                expected.trim(),
+                std::process::id()
+            ))
+        );
+    }
+
+    #[test]
+    fn connections_from_other_computers_have_no_application() {
+        let result =
+            ProcessIdentifierReal {}.application(SocketAddr::from_str("1.2.3.4:5678").unwrap());
+
+        assert_eq!(result, None);
+    }
+}
@@ -1,11 +1,12 @@
 // Copyright (c) 2017-2019, Substratum LLC (https://substratum.net) and/or its affiliates. All rights reserved.
 use crate::proxy_server::http_protocol_pack::HttpProtocolPack;
+use crate::proxy_server::smtp_protocol_pack::SmtpProtocolPack;
 use crate::proxy_server::tls_protocol_pack::TlsProtocolPack;
 use crate::sub_lib::cryptde::{PlainData, PublicKey};
 use crate::sub_lib::dispatcher::InboundClientData;
 use crate::sub_lib::logger::Logger;
 use crate::sub_lib::proxy_server::ProxyProtocol;
-use masq_lib::constants::{HTTP_PORT, TLS_PORT};
+use masq_lib::constants::{HTTP_PORT, SMTP_SUBMISSION_PORT, TLS_PORT};
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Host {
@@ -18,12 +19,16 @@ pub trait ProtocolPack: Send + Sync {
     fn standard_port(&self) -> u16;
     fn find_host(&self, data: &PlainData) -> Option<Host>;
     fn server_impersonator(&self) -> Box<dyn ServerImpersonator>;
+    // If so, the client says nothing until it hears from the server, so the stream has to be
+    // opened before there's any client data to send
+    fn server_speaks_first(&self) -> bool;
 }
 
 pub fn from_protocol(protocol: ProxyProtocol) -> Box<dyn ProtocolPack> {
     match protocol {
         ProxyProtocol::HTTP => Box::new(HttpProtocolPack {}),
         ProxyProtocol::TLS => Box::new(TlsProtocolPack {}),
+        ProxyProtocol::SMTP => Box::new(SmtpProtocolPack {}),
     }
 }
 
@@ -31,6 +36,7 @@ pub fn from_standard_port(_standard_port: u16) -> Option<Box<dyn ProtocolPack>>
     match _standard_port {
         HTTP_PORT => Some(Box::new(HttpProtocolPack {})),
         TLS_PORT => Some(Box::new(TlsProtocolPack {})),
+        SMTP_SUBMISSION_PORT => Some(Box::new(SmtpProtocolPack {})),
         _ => None,
     }
 }
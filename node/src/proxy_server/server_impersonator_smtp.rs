@@ -0,0 +1,88 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use crate::proxy_server::protocol_pack::ServerImpersonator;
+use crate::sub_lib::cryptde::PublicKey;
+
+// The client is waiting for the server's greeting, and a 421 greeting tells it that the service
+// isn't available and the connection is about to close
+pub struct ServerImpersonatorSmtp {}
+
+impl ServerImpersonator for ServerImpersonatorSmtp {
+    fn route_query_failure_response(&self, server_name: &str) -> Vec<u8> {
+        ServerImpersonatorSmtp::make_reply(&format!(
+            "4.4.0 MASQ can't find a route to {} yet",
+            server_name
+        ))
+    }
+
+    fn dns_resolution_failure_response(
+        &self,
+        exit_key: &PublicKey,
+        server_name_opt: Option<String>,
+    ) -> Vec<u8> {
+        let server_name = server_name_opt.unwrap_or_else(|| "<unspecified>".to_string());
+        ServerImpersonatorSmtp::make_reply(&format!(
+            "4.4.3 Exit Node {} couldn't resolve {}",
+            exit_key, server_name
+        ))
+    }
+
+    fn consuming_wallet_absent(&self) -> Vec<u8> {
+        ServerImpersonatorSmtp::make_reply("4.7.0 MASQ can't consume without wallet to pay from")
+    }
+}
+
+impl ServerImpersonatorSmtp {
+    fn make_reply(text: &str) -> Vec<u8> {
+        format!("421 {}\r\n", text).into_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn route_query_failure_response_produces_service_unavailable_reply() {
+        let subject = ServerImpersonatorSmtp {};
+
+        let result = subject.route_query_failure_response("smtp.example.com");
+
+        assert_eq!(
+            String::from_utf8(result).unwrap(),
+            "421 4.4.0 MASQ can't find a route to smtp.example.com yet\r\n"
+        );
+    }
+
+    #[test]
+    fn dns_resolution_failure_response_produces_service_unavailable_reply() {
+        let subject = ServerImpersonatorSmtp {};
+
+        let with_name = subject.dns_resolution_failure_response(
+            &PublicKey::new(&b"exit"[..]),
+            Some("smtp.example.com".to_string()),
+        );
+        let without_name =
+            subject.dns_resolution_failure_response(&PublicKey::new(&b"exit"[..]), None);
+
+        assert_eq!(
+            String::from_utf8(with_name).unwrap(),
+            "421 4.4.3 Exit Node ZXhpdA couldn't resolve smtp.example.com\r\n"
+        );
+        assert_eq!(
+            String::from_utf8(without_name).unwrap(),
+            "421 4.4.3 Exit Node ZXhpdA couldn't resolve <unspecified>\r\n"
+        );
+    }
+
+    #[test]
+    fn consuming_wallet_absent_produces_service_unavailable_reply() {
+        let subject = ServerImpersonatorSmtp {};
+
+        let result = subject.consuming_wallet_absent();
+
+        assert_eq!(
+            String::from_utf8(result).unwrap(),
+            "421 4.7.0 MASQ can't consume without wallet to pay from\r\n"
+        );
+    }
+}
@@ -0,0 +1,55 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use crate::proxy_server::protocol_pack::{Host, ProtocolPack, ServerImpersonator};
+use crate::proxy_server::server_impersonator_smtp::ServerImpersonatorSmtp;
+use crate::sub_lib::cryptde::PlainData;
+use crate::sub_lib::proxy_server::ProxyProtocol;
+use masq_lib::constants::SMTP_SUBMISSION_PORT;
+
+pub struct SmtpProtocolPack {}
+
+impl ProtocolPack for SmtpProtocolPack {
+    fn proxy_protocol(&self) -> ProxyProtocol {
+        ProxyProtocol::SMTP
+    }
+
+    fn standard_port(&self) -> u16 {
+        SMTP_SUBMISSION_PORT
+    }
+
+    // An SMTP client never names the server it's talking to, so the host has to come from the
+    // tunnel the client opened
+    fn find_host(&self, _data: &PlainData) -> Option<Host> {
+        None
+    }
+
+    fn server_impersonator(&self) -> Box<dyn ServerImpersonator> {
+        Box::new(ServerImpersonatorSmtp {})
+    }
+
+    fn server_speaks_first(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn knows_its_protocol_and_port() {
+        let subject = SmtpProtocolPack {};
+
+        assert_eq!(subject.proxy_protocol(), ProxyProtocol::SMTP);
+        assert_eq!(subject.standard_port(), SMTP_SUBMISSION_PORT);
+        assert_eq!(subject.server_speaks_first(), true);
+    }
+
+    #[test]
+    fn finds_no_host_in_client_data() {
+        let subject = SmtpProtocolPack {};
+
+        let result = subject.find_host(&PlainData::new(b"EHLO client.example.com\r\n"));
+
+        assert_eq!(result, None);
+    }
+}
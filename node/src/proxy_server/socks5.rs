@@ -0,0 +1,690 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use crate::proxy_server::protocol_pack::Host;
+use crate::sub_lib::proxy_server::SocksCredentials;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+const SOCKS_VERSION: u8 = 0x05;
+const USERNAME_PASSWORD_VERSION: u8 = 0x01;
+
+const NO_AUTHENTICATION_REQUIRED: u8 = 0x00;
+const USERNAME_PASSWORD: u8 = 0x02;
+const NO_ACCEPTABLE_METHODS: u8 = 0xFF;
+
+const CONNECT: u8 = 0x01;
+const UDP_ASSOCIATE: u8 = 0x03;
+
+const IPV4_ADDRESS: u8 = 0x01;
+const DOMAIN_NAME: u8 = 0x03;
+const IPV6_ADDRESS: u8 = 0x04;
+
+const SUCCEEDED: u8 = 0x00;
+const GENERAL_FAILURE: u8 = 0x01;
+const COMMAND_NOT_SUPPORTED: u8 = 0x07;
+const ADDRESS_TYPE_NOT_SUPPORTED: u8 = 0x08;
+
+const AUTHENTICATION_SUCCEEDED: u8 = 0x00;
+const AUTHENTICATION_FAILED: u8 = 0x01;
+
+#[derive(Debug, PartialEq)]
+pub enum Socks5Outcome {
+    // The negotiation isn't over yet; send these bytes (if any) back to the client
+    Negotiating(Vec<u8>),
+    // The client wants a tunnel to this Host; send these bytes back, then start tunneling. The last
+    // bytes are whatever the client sent after its request without waiting for the reply: they're
+    // the first data for the tunnel.
+    Connected(Vec<u8>, Host, Vec<u8>),
+    // The client wants to relay UDP datagrams from this port (0 if it doesn't know yet); send these
+    // bytes back, then accept datagrams for as long as the stream stays open
+    Associated(Vec<u8>, u16),
+    // The negotiation failed; send these bytes back, then close the stream
+    Refused(Vec<u8>),
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Socks5State {
+    AwaitingGreeting,
+    AwaitingCredentials,
+    AwaitingRequest,
+    Finished,
+}
+
+enum Socks5Step {
+    Continue(Vec<u8>),
+    Connect(Vec<u8>, Host),
+    Associate(Vec<u8>, u16),
+    Refuse(Vec<u8>),
+}
+
+enum Socks5Address {
+    Incomplete,
+    UnsupportedType,
+    // Name (None if unparseable), port, and number of bytes taken up, including the address type
+    Complete(Option<String>, u16, usize),
+}
+
+// Server side of the RFC 1928 SOCKS5 handshake, including RFC 1929 username/password
+// authentication. CONNECT is always supported; UDP ASSOCIATE only if there's a UDP relay.
+pub struct Socks5Negotiation {
+    credentials_opt: Option<SocksCredentials>,
+    udp_relay_port_opt: Option<u16>,
+    state: Socks5State,
+    data_so_far: Vec<u8>,
+}
+
+impl Socks5Negotiation {
+    pub fn new(credentials_opt: Option<SocksCredentials>) -> Socks5Negotiation {
+        Socks5Negotiation {
+            credentials_opt,
+            udp_relay_port_opt: None,
+            state: Socks5State::AwaitingGreeting,
+            data_so_far: vec![],
+        }
+    }
+
+    pub fn with_udp_relay_port(mut self, udp_relay_port: u16) -> Socks5Negotiation {
+        self.udp_relay_port_opt = Some(udp_relay_port);
+        self
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.state == Socks5State::Finished
+    }
+
+    pub fn add_data(&mut self, data: &[u8]) -> Socks5Outcome {
+        self.data_so_far.extend(data);
+        let mut reply = vec![];
+        loop {
+            let step_opt = match self.state {
+                Socks5State::AwaitingGreeting => self.take_greeting(),
+                Socks5State::AwaitingCredentials => self.take_credentials(),
+                Socks5State::AwaitingRequest => self.take_request(),
+                Socks5State::Finished => None,
+            };
+            match step_opt {
+                None => return Socks5Outcome::Negotiating(reply),
+                Some(Socks5Step::Continue(bytes)) => reply.extend(bytes),
+                Some(Socks5Step::Connect(bytes, host)) => {
+                    reply.extend(bytes);
+                    self.state = Socks5State::Finished;
+                    let early_data = std::mem::take(&mut self.data_so_far);
+                    return Socks5Outcome::Connected(reply, host, early_data);
+                }
+                Some(Socks5Step::Associate(bytes, client_port)) => {
+                    reply.extend(bytes);
+                    self.state = Socks5State::Finished;
+                    return Socks5Outcome::Associated(reply, client_port);
+                }
+                Some(Socks5Step::Refuse(bytes)) => {
+                    reply.extend(bytes);
+                    self.state = Socks5State::Finished;
+                    return Socks5Outcome::Refused(reply);
+                }
+            }
+        }
+    }
+
+    fn take_greeting(&mut self) -> Option<Socks5Step> {
+        let data = &self.data_so_far;
+        if data.len() < 2 {
+            return None;
+        }
+        if data[0] != SOCKS_VERSION {
+            return Some(Socks5Step::Refuse(vec![
+                SOCKS_VERSION,
+                NO_ACCEPTABLE_METHODS,
+            ]));
+        }
+        let method_count = data[1] as usize;
+        if data.len() < 2 + method_count {
+            return None;
+        }
+        let required_method = match self.credentials_opt {
+            Some(_) => USERNAME_PASSWORD,
+            None => NO_AUTHENTICATION_REQUIRED,
+        };
+        let acceptable = data[2..(2 + method_count)].contains(&required_method);
+        self.data_so_far = self.data_so_far.split_off(2 + method_count);
+        if !acceptable {
+            return Some(Socks5Step::Refuse(vec![
+                SOCKS_VERSION,
+                NO_ACCEPTABLE_METHODS,
+            ]));
+        }
+        self.state = match self.credentials_opt {
+            Some(_) => Socks5State::AwaitingCredentials,
+            None => Socks5State::AwaitingRequest,
+        };
+        Some(Socks5Step::Continue(vec![SOCKS_VERSION, required_method]))
+    }
+
+    fn take_credentials(&mut self) -> Option<Socks5Step> {
+        let data = &self.data_so_far;
+        if data.len() < 2 {
+            return None;
+        }
+        if data[0] != USERNAME_PASSWORD_VERSION {
+            return Some(Socks5Step::Refuse(vec![
+                USERNAME_PASSWORD_VERSION,
+                AUTHENTICATION_FAILED,
+            ]));
+        }
+        let username_len = data[1] as usize;
+        if data.len() < 3 + username_len {
+            return None;
+        }
+        let password_len = data[2 + username_len] as usize;
+        let total_len = 3 + username_len + password_len;
+        if data.len() < total_len {
+            return None;
+        }
+        let username = &data[2..(2 + username_len)];
+        let password = &data[(3 + username_len)..total_len];
+        let authenticated = match &self.credentials_opt {
+            Some(credentials) => {
+                credentials.username.as_bytes() == username
+                    && credentials.password.as_bytes() == password
+            }
+            None => false,
+        };
+        self.data_so_far = self.data_so_far.split_off(total_len);
+        if authenticated {
+            self.state = Socks5State::AwaitingRequest;
+            Some(Socks5Step::Continue(vec![
+                USERNAME_PASSWORD_VERSION,
+                AUTHENTICATION_SUCCEEDED,
+            ]))
+        } else {
+            Some(Socks5Step::Refuse(vec![
+                USERNAME_PASSWORD_VERSION,
+                AUTHENTICATION_FAILED,
+            ]))
+        }
+    }
+
+    fn take_request(&mut self) -> Option<Socks5Step> {
+        let data = &self.data_so_far;
+        if data.len() < 5 {
+            return None;
+        }
+        if data[0] != SOCKS_VERSION {
+            return Some(Socks5Step::Refuse(Self::request_reply(GENERAL_FAILURE)));
+        }
+        let command = data[1];
+        let (name_opt, port, address_len) = match Self::parse_address(&data[3..]) {
+            Socks5Address::Incomplete => return None,
+            Socks5Address::UnsupportedType => {
+                return Some(Socks5Step::Refuse(Self::request_reply(
+                    ADDRESS_TYPE_NOT_SUPPORTED,
+                )))
+            }
+            Socks5Address::Complete(name_opt, port, address_len) => (name_opt, port, address_len),
+        };
+        self.data_so_far = self.data_so_far.split_off(3 + address_len);
+        match (command, self.udp_relay_port_opt, name_opt) {
+            (CONNECT, _, Some(name)) => Some(Socks5Step::Connect(
+                Self::request_reply(SUCCEEDED),
+                Host {
+                    name,
+                    port: Some(port),
+                },
+            )),
+            (CONNECT, _, None) => Some(Socks5Step::Refuse(Self::request_reply(GENERAL_FAILURE))),
+            // The address in a UDP ASSOCIATE request is only a hint about where datagrams will come from
+            (UDP_ASSOCIATE, Some(udp_relay_port), _) => Some(Socks5Step::Associate(
+                Self::reply_with_address(
+                    SUCCEEDED,
+                    SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), udp_relay_port),
+                ),
+                port,
+            )),
+            _ => Some(Socks5Step::Refuse(Self::request_reply(
+                COMMAND_NOT_SUPPORTED,
+            ))),
+        }
+    }
+
+    fn parse_address(data: &[u8]) -> Socks5Address {
+        if data.len() < 2 {
+            return Socks5Address::Incomplete;
+        }
+        let (address_offset, address_len) = match data[0] {
+            IPV4_ADDRESS => (1, 4),
+            IPV6_ADDRESS => (1, 16),
+            DOMAIN_NAME => (2, data[1] as usize),
+            _ => return Socks5Address::UnsupportedType,
+        };
+        let port_offset = address_offset + address_len;
+        if data.len() < port_offset + 2 {
+            return Socks5Address::Incomplete;
+        }
+        let address = &data[address_offset..port_offset];
+        let name_opt = match data[0] {
+            IPV4_ADDRESS => {
+                Some(Ipv4Addr::new(address[0], address[1], address[2], address[3]).to_string())
+            }
+            IPV6_ADDRESS => {
+                let mut octets = [0u8; 16];
+                octets.copy_from_slice(address);
+                Some(Ipv6Addr::from(octets).to_string())
+            }
+            _ => String::from_utf8(address.to_vec()).ok(),
+        };
+        let port = ((data[port_offset] as u16) << 8) | (data[port_offset + 1] as u16);
+        Socks5Address::Complete(name_opt, port, port_offset + 2)
+    }
+
+    fn request_reply(reply_code: u8) -> Vec<u8> {
+        // The bound address and port mean nothing here, since the real connection is made by the exit Node.
+        Self::reply_with_address(
+            reply_code,
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0),
+        )
+    }
+
+    fn reply_with_address(reply_code: u8, address: SocketAddr) -> Vec<u8> {
+        let mut reply = vec![SOCKS_VERSION, reply_code, 0x00];
+        append_address(&mut reply, address);
+        reply
+    }
+}
+
+// After UDP ASSOCIATE, every datagram between the client and the relay starts with a header
+// naming the far end (RFC 1928, section 7). Fragmented datagrams are not supported.
+pub fn parse_udp_datagram(datagram: &[u8]) -> Result<(Host, &[u8]), String> {
+    if datagram.len() < 4 {
+        return Err(format!("{}-byte datagram is too short", datagram.len()));
+    }
+    if datagram[2] != 0x00 {
+        return Err("Fragmented datagrams are not supported".to_string());
+    }
+    match Socks5Negotiation::parse_address(&datagram[3..]) {
+        Socks5Address::Complete(Some(name), port, address_len) => Ok((
+            Host {
+                name,
+                port: Some(port),
+            },
+            &datagram[(3 + address_len)..],
+        )),
+        Socks5Address::Complete(None, _, _) => {
+            Err("Datagram destination is not a valid domain name".to_string())
+        }
+        Socks5Address::UnsupportedType => {
+            Err(format!("Datagram has unknown address type {}", datagram[3]))
+        }
+        Socks5Address::Incomplete => Err("Datagram header is truncated".to_string()),
+    }
+}
+
+pub fn make_udp_datagram(source: SocketAddr, data: &[u8]) -> Vec<u8> {
+    let mut datagram = vec![0x00, 0x00, 0x00];
+    append_address(&mut datagram, source);
+    datagram.extend(data);
+    datagram
+}
+
+fn append_address(bytes: &mut Vec<u8>, address: SocketAddr) {
+    match address.ip() {
+        IpAddr::V4(ip) => {
+            bytes.push(IPV4_ADDRESS);
+            bytes.extend(&ip.octets());
+        }
+        IpAddr::V6(ip) => {
+            bytes.push(IPV6_ADDRESS);
+            bytes.extend(&ip.octets());
+        }
+    }
+    bytes.extend(&address.port().to_be_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn credentials() -> SocksCredentials {
+        SocksCredentials {
+            username: "booga".to_string(),
+            password: "agoob".to_string(),
+        }
+    }
+
+    fn reply(reply_code: u8) -> Vec<u8> {
+        vec![
+            0x05, reply_code, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ]
+    }
+
+    #[test]
+    fn new_negotiation_is_not_finished() {
+        let subject = Socks5Negotiation::new(None);
+
+        assert_eq!(subject.is_finished(), false);
+    }
+
+    #[test]
+    fn unauthenticated_connect_to_domain_name_succeeds() {
+        let mut subject = Socks5Negotiation::new(None);
+
+        let greeting_result = subject.add_data(&[0x05, 0x02, 0x02, 0x00]);
+        let request_result = subject.add_data(&[
+            0x05, 0x01, 0x00, 0x03, 0x0B, b'e', b'x', b'a', b'm', b'p', b'l', b'e', b'.', b'c',
+            b'o', b'm', 0x01, 0xBB,
+        ]);
+
+        assert_eq!(
+            greeting_result,
+            Socks5Outcome::Negotiating(vec![0x05, 0x00])
+        );
+        assert_eq!(
+            request_result,
+            Socks5Outcome::Connected(
+                reply(0x00),
+                Host {
+                    name: "example.com".to_string(),
+                    port: Some(443),
+                },
+                vec![]
+            )
+        );
+        assert_eq!(subject.is_finished(), true);
+    }
+
+    #[test]
+    fn connect_to_ipv4_address_succeeds() {
+        let mut subject = Socks5Negotiation::new(None);
+        subject.add_data(&[0x05, 0x01, 0x00]);
+
+        let result = subject.add_data(&[0x05, 0x01, 0x00, 0x01, 1, 2, 3, 4, 0x00, 0x50]);
+
+        assert_eq!(
+            result,
+            Socks5Outcome::Connected(
+                reply(0x00),
+                Host {
+                    name: "1.2.3.4".to_string(),
+                    port: Some(80),
+                },
+                vec![]
+            )
+        );
+    }
+
+    #[test]
+    fn connect_to_ipv6_address_succeeds() {
+        let mut subject = Socks5Negotiation::new(None);
+        subject.add_data(&[0x05, 0x01, 0x00]);
+        let mut request = vec![0x05, 0x01, 0x00, 0x04];
+        request.extend(&[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+        request.extend(&[0x1F, 0x90]);
+
+        let result = subject.add_data(&request);
+
+        assert_eq!(
+            result,
+            Socks5Outcome::Connected(
+                reply(0x00),
+                Host {
+                    name: "::1".to_string(),
+                    port: Some(8080),
+                },
+                vec![]
+            )
+        );
+    }
+
+    #[test]
+    fn greeting_and_request_in_the_same_chunk_produce_both_replies() {
+        let mut subject = Socks5Negotiation::new(None);
+
+        let result = subject.add_data(&[
+            0x05, 0x01, 0x00, 0x05, 0x01, 0x00, 0x03, 0x01, b'x', 0x00, 0x50,
+        ]);
+
+        let mut expected_reply = vec![0x05, 0x00];
+        expected_reply.extend(reply(0x00));
+        assert_eq!(
+            result,
+            Socks5Outcome::Connected(
+                expected_reply,
+                Host {
+                    name: "x".to_string(),
+                    port: Some(80),
+                },
+                vec![]
+            )
+        );
+    }
+
+    #[test]
+    fn data_sent_right_behind_the_request_comes_back_for_the_tunnel() {
+        let mut subject = Socks5Negotiation::new(None);
+        subject.add_data(&[0x05, 0x01, 0x00]);
+
+        let result = subject.add_data(&[
+            0x05, 0x01, 0x00, 0x01, 1, 2, 3, 4, 0x00, 0x50, b'G', b'E', b'T', b' ', b'/',
+        ]);
+
+        assert_eq!(
+            result,
+            Socks5Outcome::Connected(
+                reply(0x00),
+                Host {
+                    name: "1.2.3.4".to_string(),
+                    port: Some(80),
+                },
+                b"GET /".to_vec()
+            )
+        );
+        assert_eq!(subject.is_finished(), true);
+    }
+
+    #[test]
+    fn fragmented_messages_are_reassembled() {
+        let mut subject = Socks5Negotiation::new(None);
+
+        let first_result = subject.add_data(&[0x05]);
+        let second_result = subject.add_data(&[0x01, 0x00, 0x05, 0x01, 0x00, 0x01, 1, 2]);
+        let third_result = subject.add_data(&[3, 4, 0x00, 0x50]);
+
+        assert_eq!(first_result, Socks5Outcome::Negotiating(vec![]));
+        assert_eq!(second_result, Socks5Outcome::Negotiating(vec![0x05, 0x00]));
+        assert_eq!(
+            third_result,
+            Socks5Outcome::Connected(
+                reply(0x00),
+                Host {
+                    name: "1.2.3.4".to_string(),
+                    port: Some(80),
+                },
+                vec![]
+            )
+        );
+    }
+
+    #[test]
+    fn wrong_socks_version_is_refused() {
+        let mut subject = Socks5Negotiation::new(None);
+
+        let result = subject.add_data(&[0x04, 0x01, 0x00, 0x50, 1, 2, 3, 4, 0x00]);
+
+        assert_eq!(result, Socks5Outcome::Refused(vec![0x05, 0xFF]));
+        assert_eq!(subject.is_finished(), true);
+    }
+
+    #[test]
+    fn client_that_cannot_authenticate_is_refused_when_credentials_are_required() {
+        let mut subject = Socks5Negotiation::new(Some(credentials()));
+
+        let result = subject.add_data(&[0x05, 0x01, 0x00]);
+
+        assert_eq!(result, Socks5Outcome::Refused(vec![0x05, 0xFF]));
+    }
+
+    #[test]
+    fn client_that_wants_to_authenticate_is_refused_when_credentials_are_not_configured() {
+        let mut subject = Socks5Negotiation::new(None);
+
+        let result = subject.add_data(&[0x05, 0x01, 0x02]);
+
+        assert_eq!(result, Socks5Outcome::Refused(vec![0x05, 0xFF]));
+    }
+
+    #[test]
+    fn correct_credentials_are_accepted() {
+        let mut subject = Socks5Negotiation::new(Some(credentials()));
+
+        let greeting_result = subject.add_data(&[0x05, 0x02, 0x00, 0x02]);
+        let credentials_result = subject.add_data(&[
+            0x01, 0x05, b'b', b'o', b'o', b'g', b'a', 0x05, b'a', b'g', b'o', b'o', b'b',
+        ]);
+        let request_result = subject.add_data(&[0x05, 0x01, 0x00, 0x01, 1, 2, 3, 4, 0x00, 0x50]);
+
+        assert_eq!(
+            greeting_result,
+            Socks5Outcome::Negotiating(vec![0x05, 0x02])
+        );
+        assert_eq!(
+            credentials_result,
+            Socks5Outcome::Negotiating(vec![0x01, 0x00])
+        );
+        assert_eq!(
+            request_result,
+            Socks5Outcome::Connected(
+                reply(0x00),
+                Host {
+                    name: "1.2.3.4".to_string(),
+                    port: Some(80),
+                },
+                vec![]
+            )
+        );
+    }
+
+    #[test]
+    fn incorrect_credentials_are_refused() {
+        let mut subject = Socks5Negotiation::new(Some(credentials()));
+        subject.add_data(&[0x05, 0x01, 0x02]);
+
+        let result = subject.add_data(&[
+            0x01, 0x05, b'b', b'o', b'o', b'g', b'a', 0x05, b'w', b'r', b'o', b'n', b'g',
+        ]);
+
+        assert_eq!(result, Socks5Outcome::Refused(vec![0x01, 0x01]));
+        assert_eq!(subject.is_finished(), true);
+    }
+
+    #[test]
+    fn udp_associate_is_not_supported() {
+        let mut subject = Socks5Negotiation::new(None);
+        subject.add_data(&[0x05, 0x01, 0x00]);
+
+        let result = subject.add_data(&[0x05, 0x03, 0x00, 0x01, 0, 0, 0, 0, 0x00, 0x00]);
+
+        assert_eq!(result, Socks5Outcome::Refused(reply(0x07)));
+    }
+
+    #[test]
+    fn udp_associate_is_accepted_when_there_is_a_udp_relay() {
+        let mut subject = Socks5Negotiation::new(None).with_udp_relay_port(1080);
+        subject.add_data(&[0x05, 0x01, 0x00]);
+
+        let result = subject.add_data(&[0x05, 0x03, 0x00, 0x01, 0, 0, 0, 0, 0x12, 0x34]);
+
+        assert_eq!(
+            result,
+            Socks5Outcome::Associated(
+                vec![0x05, 0x00, 0x00, 0x01, 127, 0, 0, 1, 0x04, 0x38],
+                0x1234
+            )
+        );
+        assert_eq!(subject.is_finished(), true);
+    }
+
+    #[test]
+    fn udp_datagram_header_is_parsed() {
+        let mut datagram = vec![0x00, 0x00, 0x00, 0x03, 0x0B];
+        datagram.extend(b"example.com");
+        datagram.extend(&[0x01, 0xBB]);
+        datagram.extend(b"payload");
+
+        let result = parse_udp_datagram(&datagram);
+
+        assert_eq!(
+            result,
+            Ok((
+                Host {
+                    name: "example.com".to_string(),
+                    port: Some(443),
+                },
+                &b"payload"[..]
+            ))
+        );
+    }
+
+    #[test]
+    fn bad_udp_datagrams_are_rejected() {
+        assert_eq!(
+            parse_udp_datagram(&[0x00, 0x00, 0x00]),
+            Err("3-byte datagram is too short".to_string())
+        );
+        assert_eq!(
+            parse_udp_datagram(&[0x00, 0x00, 0x01, 0x01, 1, 2, 3, 4, 0x00, 0x35]),
+            Err("Fragmented datagrams are not supported".to_string())
+        );
+        assert_eq!(
+            parse_udp_datagram(&[0x00, 0x00, 0x00, 0x01, 1, 2, 3]),
+            Err("Datagram header is truncated".to_string())
+        );
+        assert_eq!(
+            parse_udp_datagram(&[0x00, 0x00, 0x00, 0x09, 1, 2, 3, 4, 0x00, 0x35]),
+            Err("Datagram has unknown address type 9".to_string())
+        );
+    }
+
+    #[test]
+    fn udp_datagram_header_is_made_for_responses() {
+        let ipv4_result = make_udp_datagram(SocketAddr::from_str("1.2.3.4:53").unwrap(), b"ok");
+        let ipv6_result = make_udp_datagram(SocketAddr::from_str("[::1]:443").unwrap(), b"ok");
+
+        assert_eq!(
+            ipv4_result,
+            vec![0x00, 0x00, 0x00, 0x01, 1, 2, 3, 4, 0x00, 0x35, b'o', b'k']
+        );
+        let mut expected_ipv6 = vec![0x00, 0x00, 0x00, 0x04];
+        expected_ipv6.extend(&[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+        expected_ipv6.extend(&[0x01, 0xBB, b'o', b'k']);
+        assert_eq!(ipv6_result, expected_ipv6);
+    }
+
+    #[test]
+    fn unknown_address_type_is_not_supported() {
+        let mut subject = Socks5Negotiation::new(None);
+        subject.add_data(&[0x05, 0x01, 0x00]);
+
+        let result = subject.add_data(&[0x05, 0x01, 0x00, 0x09, 0, 0, 0, 0, 0x00, 0x00]);
+
+        assert_eq!(result, Socks5Outcome::Refused(reply(0x08)));
+    }
+
+    #[test]
+    fn unparseable_domain_name_causes_general_failure() {
+        let mut subject = Socks5Negotiation::new(None);
+        subject.add_data(&[0x05, 0x01, 0x00]);
+
+        let result = subject.add_data(&[0x05, 0x01, 0x00, 0x03, 0x02, 0xFF, 0xFE, 0x00, 0x50]);
+
+        assert_eq!(result, Socks5Outcome::Refused(reply(0x01)));
+    }
+
+    #[test]
+    fn data_after_finish_produces_nothing() {
+        let mut subject = Socks5Negotiation::new(None);
+        subject.add_data(&[
+            0x05, 0x01, 0x00, 0x05, 0x01, 0x00, 0x01, 1, 2, 3, 4, 0x00, 0x50,
+        ]);
+
+        let result = subject.add_data(&[0x05, 0x01, 0x00]);
+
+        assert_eq!(result, Socks5Outcome::Negotiating(vec![]));
+    }
+}
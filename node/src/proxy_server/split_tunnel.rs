@@ -0,0 +1,291 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SplitTunnelRule {
+    // Matches the named domain and all of its subdomains
+    Domain(String),
+    // Matches destinations specified by IP address that fall into this network
+    Cidr(IpAddr, u8),
+}
+
+impl FromStr for SplitTunnelRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rule = s.trim();
+        if rule.is_empty() {
+            return Err("Split-tunnel rule cannot be empty".to_string());
+        }
+        let (address_str, prefix_str_opt) = match rule.find('/') {
+            Some(idx) => (&rule[..idx], Some(&rule[(idx + 1)..])),
+            None => (rule, None),
+        };
+        match (IpAddr::from_str(address_str), prefix_str_opt) {
+            (Ok(address), prefix_str_opt) => {
+                let max_prefix = if address.is_ipv4() { 32 } else { 128 };
+                let prefix = match prefix_str_opt {
+                    None => max_prefix,
+                    Some(prefix_str) => match u8::from_str(prefix_str) {
+                        Ok(prefix) if prefix <= max_prefix => prefix,
+                        _ => {
+                            return Err(format!(
+                                "Split-tunnel rule '{}' has an invalid network prefix",
+                                rule
+                            ))
+                        }
+                    },
+                };
+                Ok(SplitTunnelRule::Cidr(address, prefix))
+            }
+            (Err(_), Some(_)) => Err(format!(
+                "Split-tunnel rule '{}' is not a valid CIDR network",
+                rule
+            )),
+            (Err(_), None) => {
+                let domain = rule.trim_start_matches("*.").to_lowercase();
+                let is_bad_label = |label: &str| {
+                    label.is_empty()
+                        || !label
+                            .chars()
+                            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+                };
+                if domain.split('.').any(is_bad_label) {
+                    Err(format!(
+                        "Split-tunnel rule '{}' is not a valid domain name",
+                        rule
+                    ))
+                } else {
+                    Ok(SplitTunnelRule::Domain(domain))
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for SplitTunnelRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SplitTunnelRule::Domain(domain) => write!(f, "{}", domain),
+            SplitTunnelRule::Cidr(address, prefix) => write!(f, "{}/{}", address, prefix),
+        }
+    }
+}
+
+impl SplitTunnelRule {
+    pub fn matches(&self, hostname: &str) -> bool {
+        match self {
+            SplitTunnelRule::Domain(domain) => {
+                let hostname = hostname.trim_end_matches('.').to_lowercase();
+                hostname == *domain || hostname.ends_with(&format!(".{}", domain))
+            }
+            SplitTunnelRule::Cidr(network, prefix) => match IpAddr::from_str(hostname) {
+                Ok(address) => Self::in_network(&address, network, *prefix),
+                Err(_) => false,
+            },
+        }
+    }
+
+    fn in_network(address: &IpAddr, network: &IpAddr, prefix: u8) -> bool {
+        let (address_bits, network_bits, width) = match (address, network) {
+            (IpAddr::V4(a), IpAddr::V4(n)) => (u32::from(*a) as u128, u32::from(*n) as u128, 32u32),
+            (IpAddr::V6(a), IpAddr::V6(n)) => (u128::from(*a), u128::from(*n), 128u32),
+            _ => return false,
+        };
+        if prefix == 0 {
+            return true;
+        }
+        let shift = width - prefix as u32;
+        (address_bits >> shift) == (network_bits >> shift)
+    }
+}
+
+// If there are no rules, split tunneling is off and everything goes through MASQ.
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct SplitTunnelRules {
+    rules: Vec<SplitTunnelRule>,
+}
+
+impl FromStr for SplitTunnelRules {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rule_strs = s
+            .split(',')
+            .filter(|rule_str| !rule_str.trim().is_empty())
+            .collect::<Vec<&str>>();
+        Self::from_strs(&rule_strs)
+    }
+}
+
+impl fmt::Display for SplitTunnelRules {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_strings().join(","))
+    }
+}
+
+impl SplitTunnelRules {
+    pub fn new(rules: Vec<SplitTunnelRule>) -> SplitTunnelRules {
+        SplitTunnelRules { rules }
+    }
+
+    pub fn from_strs<S: AsRef<str>>(rule_strs: &[S]) -> Result<SplitTunnelRules, String> {
+        let rules = rule_strs
+            .iter()
+            .map(|rule_str| SplitTunnelRule::from_str(rule_str.as_ref()))
+            .collect::<Result<Vec<SplitTunnelRule>, String>>()?;
+        Ok(SplitTunnelRules::new(rules))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    pub fn to_strings(&self) -> Vec<String> {
+        self.rules.iter().map(|rule| rule.to_string()).collect()
+    }
+
+    pub fn routes_through_masq(&self, hostname_opt: Option<&str>) -> bool {
+        if self.rules.is_empty() {
+            return true;
+        }
+        match hostname_opt {
+            Some(hostname) => self.rules.iter().any(|rule| rule.matches(hostname)),
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    #[test]
+    fn domain_rule_is_parsed_and_normalized() {
+        let result = SplitTunnelRule::from_str(" *.Example.COM ");
+
+        assert_eq!(
+            result,
+            Ok(SplitTunnelRule::Domain("example.com".to_string()))
+        );
+    }
+
+    #[test]
+    fn cidr_rules_are_parsed() {
+        assert_eq!(
+            SplitTunnelRule::from_str("10.0.0.0/8"),
+            Ok(SplitTunnelRule::Cidr(
+                IpAddr::V4(Ipv4Addr::new(10, 0, 0, 0)),
+                8
+            ))
+        );
+        assert_eq!(
+            SplitTunnelRule::from_str("1.2.3.4"),
+            Ok(SplitTunnelRule::Cidr(
+                IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4)),
+                32
+            ))
+        );
+        assert_eq!(
+            SplitTunnelRule::from_str("2001:db8::/32"),
+            Ok(SplitTunnelRule::Cidr(
+                IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 0)),
+                32
+            ))
+        );
+    }
+
+    #[test]
+    fn bad_rules_are_rejected() {
+        assert_eq!(
+            SplitTunnelRule::from_str(""),
+            Err("Split-tunnel rule cannot be empty".to_string())
+        );
+        assert_eq!(
+            SplitTunnelRule::from_str("10.0.0.0/33"),
+            Err("Split-tunnel rule '10.0.0.0/33' has an invalid network prefix".to_string())
+        );
+        assert_eq!(
+            SplitTunnelRule::from_str("booga/8"),
+            Err("Split-tunnel rule 'booga/8' is not a valid CIDR network".to_string())
+        );
+        assert_eq!(
+            SplitTunnelRule::from_str("boo ga.com"),
+            Err("Split-tunnel rule 'boo ga.com' is not a valid domain name".to_string())
+        );
+        assert_eq!(
+            SplitTunnelRule::from_str("booga..com"),
+            Err("Split-tunnel rule 'booga..com' is not a valid domain name".to_string())
+        );
+    }
+
+    #[test]
+    fn domain_rule_matches_domain_and_subdomains_only() {
+        let subject = SplitTunnelRule::from_str("example.com").unwrap();
+
+        assert_eq!(subject.matches("example.com"), true);
+        assert_eq!(subject.matches("WWW.Example.com."), true);
+        assert_eq!(subject.matches("deep.www.example.com"), true);
+        assert_eq!(subject.matches("notexample.com"), false);
+        assert_eq!(subject.matches("example.com.evil.org"), false);
+    }
+
+    #[test]
+    fn cidr_rule_matches_addresses_in_network_only() {
+        let subject = SplitTunnelRule::from_str("192.168.0.0/16").unwrap();
+
+        assert_eq!(subject.matches("192.168.45.67"), true);
+        assert_eq!(subject.matches("192.169.0.1"), false);
+        assert_eq!(subject.matches("::ffff:c0a8:0101"), false);
+        assert_eq!(subject.matches("192.168.example.com"), false);
+        assert_eq!(
+            SplitTunnelRule::from_str("0.0.0.0/0")
+                .unwrap()
+                .matches("8.8.8.8"),
+            true
+        );
+        assert_eq!(
+            SplitTunnelRule::from_str("2001:db8::/32")
+                .unwrap()
+                .matches("2001:db8:1234::1"),
+            true
+        );
+    }
+
+    #[test]
+    fn empty_rules_route_everything_through_masq() {
+        let subject = SplitTunnelRules::default();
+
+        assert_eq!(subject.is_empty(), true);
+        assert_eq!(subject.routes_through_masq(Some("example.com")), true);
+        assert_eq!(subject.routes_through_masq(None), true);
+    }
+
+    #[test]
+    fn nonempty_rules_route_only_matching_hosts_through_masq() {
+        let subject = SplitTunnelRules::from_str("example.com,10.0.0.0/8").unwrap();
+
+        assert_eq!(subject.routes_through_masq(Some("www.example.com")), true);
+        assert_eq!(subject.routes_through_masq(Some("10.1.2.3")), true);
+        assert_eq!(subject.routes_through_masq(Some("example.org")), false);
+        assert_eq!(subject.routes_through_masq(None), false);
+    }
+
+    #[test]
+    fn rules_survive_a_round_trip_through_a_string() {
+        let subject = SplitTunnelRules::from_strs(&["Example.com", " 10.0.0.0/8", "::1"]).unwrap();
+
+        let string = subject.to_string();
+        let result = SplitTunnelRules::from_str(&string).unwrap();
+
+        assert_eq!(string, "example.com,10.0.0.0/8,::1/128".to_string());
+        assert_eq!(result, subject);
+        assert_eq!(
+            SplitTunnelRules::from_str(""),
+            Ok(SplitTunnelRules::default())
+        );
+    }
+}
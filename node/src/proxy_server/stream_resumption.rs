@@ -0,0 +1,142 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use crate::sub_lib::cryptde::PublicKey;
+use crate::sub_lib::stream_key::StreamKey;
+use serde_derive::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+pub const RESUMPTION_FILE: &str = "stream_resumption.cbor";
+// Longer than this, and the exit Node has probably let the server connection go
+pub const RESUMPTION_TOKEN_TTL: Duration = Duration::from_secs(30);
+pub const RESUMPTION_CHECKPOINT_INTERVAL: Duration = Duration::from_secs(5);
+// A stream that has carried nothing for this long is between requests, not in the middle of one
+pub const RESUMPTION_QUIET_PERIOD: Duration = Duration::from_secs(3);
+
+// Everything the originating Node needs to pick an idle stream up where it left it after a
+// restart: the exit Node still has the stream, and its connection to the server, for a while.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ResumptionToken {
+    pub stream_key: StreamKey,
+    pub exit_key: PublicKey,
+    pub hostname: String,
+    pub port: u16,
+    // The next sequence number in each direction, as the exit Node counts them
+    pub next_request_sequence: u64,
+    pub next_response_sequence: u64,
+    pub expires_at: i64,
+}
+
+pub trait ResumptionStore: Send {
+    fn save(&self, tokens: &[ResumptionToken]);
+    fn load(&self) -> Vec<ResumptionToken>;
+}
+
+pub struct ResumptionStoreReal {
+    path: PathBuf,
+}
+
+impl ResumptionStore for ResumptionStoreReal {
+    // Written aside and renamed into place, so that a crash mid-write leaves the last checkpoint
+    fn save(&self, tokens: &[ResumptionToken]) {
+        let temp_path = self.path.with_extension("tmp");
+        let data = serde_cbor::to_vec(&tokens.to_vec()).expect("Can't serialize resumption tokens");
+        let result = File::create(&temp_path)
+            .and_then(|mut file| file.write_all(&data).and_then(|_| file.sync_data()))
+            .and_then(|_| std::fs::rename(&temp_path, &self.path));
+        if let Err(e) = result {
+            panic!("Can't write resumption tokens {:?}: {}", self.path, e)
+        }
+    }
+
+    // Resumption is a nicety: a file that can't be read just means nothing is resumed
+    fn load(&self) -> Vec<ResumptionToken> {
+        match std::fs::read(&self.path) {
+            Ok(data) => serde_cbor::from_slice(&data).unwrap_or_default(),
+            Err(_) => vec![],
+        }
+    }
+}
+
+impl ResumptionStoreReal {
+    pub fn new(data_directory: &Path) -> ResumptionStoreReal {
+        ResumptionStoreReal {
+            path: data_directory.join(RESUMPTION_FILE),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use masq_lib::test_utils::utils::ensure_node_home_directory_exists;
+    use std::net::SocketAddr;
+    use std::str::FromStr;
+
+    fn make_token(port: u16) -> ResumptionToken {
+        ResumptionToken {
+            stream_key: StreamKey::new(
+                PublicKey::new(b"originator"),
+                SocketAddr::from_str(&format!("1.2.3.4:{}", port)).unwrap(),
+            ),
+            exit_key: PublicKey::new(b"exit"),
+            hostname: "example.com".to_string(),
+            port: 80,
+            next_request_sequence: 3,
+            next_response_sequence: 12,
+            expires_at: 1_600_000_000,
+        }
+    }
+
+    #[test]
+    fn saved_tokens_can_be_loaded_by_a_later_run() {
+        let data_dir = ensure_node_home_directory_exists(
+            "stream_resumption",
+            "saved_tokens_can_be_loaded_by_a_later_run",
+        );
+        let tokens = vec![make_token(1111), make_token(2222)];
+        ResumptionStoreReal::new(&data_dir).save(&tokens);
+
+        let result = ResumptionStoreReal::new(&data_dir).load();
+
+        assert_eq!(result, tokens);
+        assert_eq!(
+            data_dir
+                .join(RESUMPTION_FILE)
+                .with_extension("tmp")
+                .exists(),
+            false
+        );
+    }
+
+    #[test]
+    fn a_later_save_replaces_an_earlier_one() {
+        let data_dir = ensure_node_home_directory_exists(
+            "stream_resumption",
+            "a_later_save_replaces_an_earlier_one",
+        );
+        let subject = ResumptionStoreReal::new(&data_dir);
+        subject.save(&[make_token(1111)]);
+
+        subject.save(&[]);
+
+        assert_eq!(subject.load(), vec![]);
+    }
+
+    #[test]
+    fn a_missing_or_unreadable_file_resumes_nothing() {
+        let data_dir = ensure_node_home_directory_exists(
+            "stream_resumption",
+            "a_missing_or_unreadable_file_resumes_nothing",
+        );
+        let subject = ResumptionStoreReal::new(&data_dir);
+        let missing = subject.load();
+        std::fs::write(data_dir.join(RESUMPTION_FILE), b"booga").unwrap();
+
+        let unreadable = subject.load();
+
+        assert_eq!(missing, vec![]);
+        assert_eq!(unreadable, vec![]);
+    }
+}
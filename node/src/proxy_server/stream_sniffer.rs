@@ -0,0 +1,241 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use lazy_static::lazy_static;
+use rustc_hex::ToHex;
+use serde_json::json;
+use std::collections::VecDeque;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::RwLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// The start of a stream is enough to tell what protocol it speaks
+pub const SNIFF_SAMPLE_BYTES: usize = 512;
+pub const MAX_SNIFFED_STREAMS: usize = 100;
+
+const PCAP_MAGIC: u32 = 0xa1b2_c3d4;
+// Packets that start with their IP header, with no link layer in front
+const PCAP_LINKTYPE_RAW: u32 = 101;
+const TCP_PROTOCOL: u8 = 6;
+const TCP_PSH_ACK: u8 = 0x18;
+
+lazy_static! {
+    pub static ref SNIFFED_STREAMS: StreamSniffer = StreamSniffer::default();
+}
+
+// The first bytes of a stream that none of the protocol packs could find a host in. They stay in
+// this Node's memory until a diagnostics bundle is collected; they're never sent anywhere else.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SniffedStream {
+    pub captured_at: SystemTime,
+    pub client_addr: SocketAddr,
+    pub reception_port_opt: Option<u16>,
+    pub length: usize,
+    pub bytes: Vec<u8>,
+}
+
+#[derive(Default)]
+pub struct StreamSniffer {
+    samples: RwLock<VecDeque<SniffedStream>>,
+}
+
+impl StreamSniffer {
+    // Keeps the latest MAX_SNIFFED_STREAMS samples
+    pub fn capture(
+        &self,
+        client_addr: SocketAddr,
+        reception_port_opt: Option<u16>,
+        data: &[u8],
+        now: SystemTime,
+    ) {
+        let mut samples = self
+            .samples
+            .write()
+            .expect("Failed to capture unrecognized stream");
+        if samples.len() >= MAX_SNIFFED_STREAMS {
+            samples.pop_front();
+        }
+        samples.push_back(SniffedStream {
+            captured_at: now,
+            client_addr,
+            reception_port_opt,
+            length: data.len(),
+            bytes: data[..data.len().min(SNIFF_SAMPLE_BYTES)].to_vec(),
+        });
+    }
+
+    pub fn samples(&self) -> Vec<SniffedStream> {
+        self.samples
+            .read()
+            .expect("Failed to read unrecognized streams")
+            .iter()
+            .cloned()
+            .collect()
+    }
+}
+
+pub fn samples_to_json(samples: &[SniffedStream]) -> String {
+    let records = samples
+        .iter()
+        .map(|sample| {
+            json!({
+                "capturedAt": secs_and_micros(sample.captured_at).0,
+                "client": sample.client_addr.to_string(),
+                "port": sample.reception_port_opt,
+                "length": sample.length,
+                "bytes": sample.bytes.to_hex::<String>(),
+            })
+        })
+        .collect::<Vec<_>>();
+    serde_json::to_string_pretty(&records).expect("Couldn't serialize unrecognized streams")
+}
+
+// Each sample becomes one TCP segment from the client to the port it arrived on, so that Wireshark
+// can dissect it. The Node's own address isn't known here, so loopback stands in for it.
+pub fn samples_to_pcap(samples: &[SniffedStream]) -> Vec<u8> {
+    let mut pcap = vec![];
+    pcap.extend_from_slice(&PCAP_MAGIC.to_le_bytes());
+    pcap.extend_from_slice(&2u16.to_le_bytes());
+    pcap.extend_from_slice(&4u16.to_le_bytes());
+    pcap.extend_from_slice(&0i32.to_le_bytes());
+    pcap.extend_from_slice(&0u32.to_le_bytes());
+    pcap.extend_from_slice(&65535u32.to_le_bytes());
+    pcap.extend_from_slice(&PCAP_LINKTYPE_RAW.to_le_bytes());
+    samples.iter().for_each(|sample| {
+        let packet = ip_packet(sample);
+        let (secs, micros) = secs_and_micros(sample.captured_at);
+        pcap.extend_from_slice(&(secs as u32).to_le_bytes());
+        pcap.extend_from_slice(&micros.to_le_bytes());
+        pcap.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+        pcap.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+        pcap.extend_from_slice(&packet);
+    });
+    pcap
+}
+
+fn ip_packet(sample: &SniffedStream) -> Vec<u8> {
+    let mut segment = vec![];
+    segment.extend_from_slice(&sample.client_addr.port().to_be_bytes());
+    segment.extend_from_slice(&sample.reception_port_opt.unwrap_or(0).to_be_bytes());
+    segment.extend_from_slice(&1u32.to_be_bytes());
+    segment.extend_from_slice(&1u32.to_be_bytes());
+    segment.extend_from_slice(&[0x50, TCP_PSH_ACK]);
+    segment.extend_from_slice(&65535u16.to_be_bytes());
+    segment.extend_from_slice(&[0, 0, 0, 0]);
+    segment.extend_from_slice(&sample.bytes);
+    let mut packet = vec![];
+    match sample.client_addr.ip() {
+        IpAddr::V4(source) => {
+            packet.extend_from_slice(&[0x45, 0]);
+            packet.extend_from_slice(&((20 + segment.len()) as u16).to_be_bytes());
+            packet.extend_from_slice(&[0, 0, 0x40, 0, 64, TCP_PROTOCOL, 0, 0]);
+            packet.extend_from_slice(&source.octets());
+            packet.extend_from_slice(&Ipv4Addr::LOCALHOST.octets());
+            let checksum = ipv4_checksum(&packet);
+            packet[10..12].copy_from_slice(&checksum.to_be_bytes());
+        }
+        IpAddr::V6(source) => {
+            packet.extend_from_slice(&[0x60, 0, 0, 0]);
+            packet.extend_from_slice(&(segment.len() as u16).to_be_bytes());
+            packet.extend_from_slice(&[TCP_PROTOCOL, 64]);
+            packet.extend_from_slice(&source.octets());
+            packet.extend_from_slice(&Ipv6Addr::LOCALHOST.octets());
+        }
+    }
+    packet.extend_from_slice(&segment);
+    packet
+}
+
+fn ipv4_checksum(header: &[u8]) -> u16 {
+    let sum = header
+        .chunks(2)
+        .map(|pair| u32::from(u16::from_be_bytes([pair[0], pair[1]])))
+        .sum::<u32>();
+    let folded = (sum & 0xffff) + (sum >> 16);
+    !(((folded & 0xffff) + (folded >> 16)) as u16)
+}
+
+fn secs_and_micros(time: SystemTime) -> (u64, u32) {
+    let since = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    (since.as_secs(), since.subsec_micros())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use std::time::Duration;
+
+    fn sample(client: &str, data: &[u8]) -> SniffedStream {
+        SniffedStream {
+            captured_at: UNIX_EPOCH + Duration::from_micros(1_600_000_000_000_123),
+            client_addr: SocketAddr::from_str(client).unwrap(),
+            reception_port_opt: Some(80),
+            length: data.len(),
+            bytes: data.to_vec(),
+        }
+    }
+
+    #[test]
+    fn only_the_start_of_the_latest_streams_is_kept() {
+        let subject = StreamSniffer::default();
+        let client = SocketAddr::from_str("127.0.0.1:5000").unwrap();
+
+        (0..=MAX_SNIFFED_STREAMS)
+            .for_each(|n| subject.capture(client, Some(80), &[n as u8; 1000], SystemTime::now()));
+
+        let samples = subject.samples();
+        assert_eq!(samples.len(), MAX_SNIFFED_STREAMS);
+        assert_eq!(samples[0].bytes, vec![1u8; SNIFF_SAMPLE_BYTES]);
+        assert_eq!(samples[0].length, 1000);
+        assert_eq!(
+            samples[MAX_SNIFFED_STREAMS - 1].bytes,
+            vec![MAX_SNIFFED_STREAMS as u8; SNIFF_SAMPLE_BYTES]
+        );
+    }
+
+    #[test]
+    fn samples_become_tcp_segments_in_a_pcap_file() {
+        let samples = vec![
+            sample("192.168.0.2:5000", b"BOOGA"),
+            sample("[::1]:5001", b"WOOGA"),
+        ];
+
+        let result = samples_to_pcap(&samples);
+
+        assert_eq!(&result[0..4], &[0xd4, 0xc3, 0xb2, 0xa1]);
+        assert_eq!(&result[20..24], &101u32.to_le_bytes());
+        let first = &result[24..];
+        assert_eq!(&first[0..4], &1_600_000_000u32.to_le_bytes());
+        assert_eq!(&first[4..8], &123u32.to_le_bytes());
+        assert_eq!(&first[8..12], &45u32.to_le_bytes());
+        let packet = &first[16..61];
+        assert_eq!(packet[0], 0x45);
+        assert_eq!(packet[9], TCP_PROTOCOL);
+        assert_eq!(ipv4_checksum(&packet[0..20]), 0);
+        assert_eq!(&packet[12..16], &[192, 168, 0, 2]);
+        assert_eq!(&packet[16..20], &[127, 0, 0, 1]);
+        assert_eq!(&packet[20..24], &[0x13, 0x88, 0, 80]);
+        assert_eq!(&packet[40..], b"BOOGA");
+        let second = &result[24 + 16 + 45..];
+        assert_eq!(&second[8..12], &65u32.to_le_bytes());
+        assert_eq!(second[16], 0x60);
+        assert_eq!(&second[16 + 60..], b"WOOGA");
+    }
+
+    #[test]
+    fn samples_are_listed_in_json_with_their_bytes_in_hex() {
+        let samples = vec![sample("1.2.3.4:5678", b"\x16\x03\x01")];
+
+        let result: serde_json::Value = serde_json::from_str(&samples_to_json(&samples)).unwrap();
+
+        assert_eq!(
+            result,
+            json!([{
+                "capturedAt": 1_600_000_000u64,
+                "client": "1.2.3.4:5678",
+                "port": 80,
+                "length": 3,
+                "bytes": "160301",
+            }])
+        );
+    }
+}
@@ -5,6 +5,10 @@ use crate::sub_lib::binary_traverser::BinaryTraverser;
 use crate::sub_lib::cryptde::PlainData;
 use crate::sub_lib::proxy_server::ProxyProtocol;
 
+const SERVER_NAME_EXTENSION_TYPE: u16 = 0x0000;
+// encrypted_client_hello, and the draft encrypted_server_name that preceded it
+const ENCRYPTED_SERVER_NAME_EXTENSION_TYPES: [u16; 2] = [0xFE0D, 0xFFCE];
+
 pub struct TlsProtocolPack {}
 
 impl ProtocolPack for TlsProtocolPack {
@@ -33,6 +37,10 @@ impl ProtocolPack for TlsProtocolPack {
     fn server_impersonator(&self) -> Box<dyn ServerImpersonator> {
         Box::new(ServerImpersonatorTls {})
     }
+
+    fn server_speaks_first(&self) -> bool {
+        false
+    }
 }
 
 impl TlsProtocolPack {
@@ -49,9 +57,52 @@ impl TlsProtocolPack {
         xvsr.get_u8() == Ok(client_hello_message_type)
     }
 
+    // An encrypted client hello (ECH) still names a client-facing server in its server_name
+    // extension, and find_host goes by that; the older encrypted SNI (ESNI) leaves the extension
+    // out entirely, so the real server can't be found from the client hello at all.
+    pub fn hides_host(data: &PlainData) -> bool {
+        let mut xvsr = BinaryTraverser::new(data);
+        if !TlsProtocolPack::is_handshake(&mut xvsr) || !TlsProtocolPack::is_client_hello(&mut xvsr)
+        {
+            return false;
+        }
+        match Self::extension_types(&mut xvsr) {
+            Ok(types) => {
+                !types.contains(&SERVER_NAME_EXTENSION_TYPE)
+                    && types
+                        .iter()
+                        .any(|t| ENCRYPTED_SERVER_NAME_EXTENSION_TYPES.contains(t))
+            }
+            Err(()) => false,
+        }
+    }
+
     fn host_name_from_client_hello(xvsr: &mut BinaryTraverser) -> Result<String, ()> {
+        let extensions_end = Self::skip_to_extensions(xvsr)?;
+        while xvsr.offset() < extensions_end {
+            let extension_type = xvsr.get_u16()?;
+            if extension_type == SERVER_NAME_EXTENSION_TYPE {
+                return TlsProtocolPack::host_name_from_extension(xvsr);
+            }
+            let extension_length = xvsr.get_u16()?;
+            xvsr.advance(extension_length as usize)?;
+        }
+        Err(())
+    }
+
+    fn extension_types(xvsr: &mut BinaryTraverser) -> Result<Vec<u16>, ()> {
+        let extensions_end = Self::skip_to_extensions(xvsr)?;
+        let mut types = vec![];
+        while xvsr.offset() < extensions_end {
+            types.push(xvsr.get_u16()?);
+            let extension_length = xvsr.get_u16()?;
+            xvsr.advance(extension_length as usize)?;
+        }
+        Ok(types)
+    }
+
+    fn skip_to_extensions(xvsr: &mut BinaryTraverser) -> Result<usize, ()> {
         let session_id_length_position = 43;
-        let server_name_extension_type = 0u16;
         xvsr.advance(session_id_length_position - xvsr.offset())?;
         let session_id_length = xvsr.get_u8()?;
         xvsr.advance(session_id_length as usize)?;
@@ -60,16 +111,7 @@ impl TlsProtocolPack {
         let compression_methods_length = xvsr.get_u8()?;
         xvsr.advance(compression_methods_length as usize)?;
         let extensions_length = xvsr.get_u16()? as usize;
-        let extensions_offset = xvsr.offset();
-        while xvsr.offset() < (extensions_offset + extensions_length) {
-            let extension_type = xvsr.get_u16()?;
-            if extension_type == server_name_extension_type {
-                return TlsProtocolPack::host_name_from_extension(xvsr);
-            }
-            let extension_length = xvsr.get_u16()?;
-            xvsr.advance(extension_length as usize)?;
-        }
-        Err(())
+        Ok(xvsr.offset() + extensions_length)
     }
 
     fn host_name_from_extension(xvsr: &mut BinaryTraverser) -> Result<String, ()> {
@@ -792,4 +834,101 @@ mod tests {
 
         assert_eq!(None, result);
     }
+
+    #[rustfmt::skip]
+    fn make_client_hello(extensions: &[u8]) -> PlainData {
+        let mut data = vec![
+            0x16, // content_type: Handshake
+            0x00, 0x00, 0x00, 0x00, // version, length: don't care
+            0x01, // handshake_type: ClientHello
+            0x00, 0x00, 0x00, 0x00, 0x00, // length, version: don't care
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // random: don't care
+            0x00, // session_id_length
+            0x00, 0x00, // cipher_suites_length
+            0x00, // compression_methods_length
+            0x00, extensions.len() as u8, // extensions_length
+        ];
+        data.extend_from_slice(extensions);
+        PlainData::from(data)
+    }
+
+    #[rustfmt::skip]
+    const OUTER_SERVER_NAME_EXTENSION: [u8; 22] = [
+        0x00, 0x00, // extension_type: server_name
+        0x00, 0x12, // extension_length
+        0x00, 0x10, // server_name_list_length
+        0x00, // server_name_type
+        0x00, 0x0D, // server_name_length
+        b'p', b'u', b'b', b'l', b'i', b'c', b'.', b'n',
+        b'a', b'm', b'e', b'.', b'c', // server_name
+    ];
+
+    #[test]
+    fn encrypted_client_hello_goes_by_the_name_of_its_client_facing_server() {
+        let mut extensions = vec![
+            0xFE, 0x0D, // extension_type: encrypted_client_hello
+            0x00, 0x03, // extension_length
+            0x01, 0x02, 0x03, // encrypted inner client hello
+        ];
+        extensions.extend_from_slice(&OUTER_SERVER_NAME_EXTENSION);
+        let data = make_client_hello(&extensions);
+
+        let result = TlsProtocolPack {}.find_host(&data);
+
+        assert_eq!(
+            result,
+            Some(Host {
+                name: String::from("public.name.c"),
+                port: None
+            })
+        );
+        assert_eq!(TlsProtocolPack::hides_host(&data), false);
+    }
+
+    #[test]
+    fn encrypted_server_name_hides_the_host() {
+        vec![[0xFEu8, 0x0Du8], [0xFFu8, 0xCEu8]]
+            .into_iter()
+            .for_each(|extension_type| {
+                let data = make_client_hello(&[
+                    extension_type[0],
+                    extension_type[1],
+                    0x00,
+                    0x02, // extension_length
+                    0x01,
+                    0x02, // encrypted server name
+                ]);
+
+                assert_eq!(TlsProtocolPack {}.find_host(&data), None);
+                assert_eq!(
+                    TlsProtocolPack::hides_host(&data),
+                    true,
+                    "extension_type: {:?}",
+                    extension_type
+                );
+            });
+    }
+
+    #[test]
+    fn client_hello_without_a_readable_encrypted_server_name_does_not_hide_the_host() {
+        let without_server_name = make_client_hello(&[
+            0x00, 0xFF, // extension_type: not server_name
+            0x00, 0x01, // extension_length
+            0x01, // throw-away data for fake extension
+        ]);
+        let truncated = make_client_hello(&[
+            0xFF, 0xCE, // extension_type: encrypted_server_name
+            0x00, 0x09, // extension_length: too long
+            0x01,
+        ]);
+        let with_server_name = make_client_hello(&OUTER_SERVER_NAME_EXTENSION);
+
+        assert_eq!(TlsProtocolPack::hides_host(&without_server_name), false);
+        assert_eq!(TlsProtocolPack::hides_host(&truncated), false);
+        assert_eq!(TlsProtocolPack::hides_host(&with_server_name), false);
+        assert_eq!(TlsProtocolPack::hides_host(&PlainData::new(&[0x17])), false);
+    }
 }
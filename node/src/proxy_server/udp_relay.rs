@@ -0,0 +1,142 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+
+use crate::sub_lib::logger::Logger;
+use actix::Message;
+use actix::Recipient;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+use std::thread;
+
+const MAX_DATAGRAM_SIZE: usize = 65536;
+
+#[derive(Message, Clone, Debug, PartialEq)]
+pub struct InboundClientDatagram {
+    pub peer_addr: SocketAddr,
+    pub data: Vec<u8>,
+}
+
+// The localhost UDP socket that SOCKS5 clients send their UDP ASSOCIATE datagrams to
+pub trait UdpRelay: Send {
+    fn send_to(&self, datagram: &[u8], peer_addr: SocketAddr) -> io::Result<usize>;
+}
+
+pub trait UdpRelayFactory: Send {
+    fn make(
+        &self,
+        port: u16,
+        datagram_sub: Recipient<InboundClientDatagram>,
+        logger: Logger,
+    ) -> io::Result<Box<dyn UdpRelay>>;
+}
+
+pub struct UdpRelayReal {
+    socket: UdpSocket,
+}
+
+impl UdpRelay for UdpRelayReal {
+    fn send_to(&self, datagram: &[u8], peer_addr: SocketAddr) -> io::Result<usize> {
+        self.socket.send_to(datagram, peer_addr)
+    }
+}
+
+pub struct UdpRelayFactoryReal {}
+
+impl UdpRelayFactory for UdpRelayFactoryReal {
+    fn make(
+        &self,
+        port: u16,
+        datagram_sub: Recipient<InboundClientDatagram>,
+        logger: Logger,
+    ) -> io::Result<Box<dyn UdpRelay>> {
+        let socket = UdpSocket::bind(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port))?;
+        let reader = socket.try_clone()?;
+        thread::spawn(move || {
+            let mut buffer = [0u8; MAX_DATAGRAM_SIZE];
+            loop {
+                match reader.recv_from(&mut buffer) {
+                    Ok((len, peer_addr)) => {
+                        let datagram = InboundClientDatagram {
+                            peer_addr,
+                            data: buffer[..len].to_vec(),
+                        };
+                        if datagram_sub.try_send(datagram).is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        error!(logger, "Closing SOCKS UDP relay on port {}: {}", port, e);
+                        break;
+                    }
+                }
+            }
+        });
+        Ok(Box::new(UdpRelayReal { socket }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::recorder::make_recorder;
+    use actix::{Actor, System};
+    use masq_lib::utils::find_free_port;
+    use std::time::Duration;
+
+    #[test]
+    fn udp_relay_forwards_client_datagrams() {
+        let port = find_free_port();
+        let (recorder, awaiter, recording_arc) = make_recorder();
+        let (tx, rx) = std::sync::mpsc::channel();
+        thread::spawn(move || {
+            let system = System::new("udp_relay_forwards_client_datagrams");
+            let datagram_sub = recorder.start().recipient::<InboundClientDatagram>();
+            let _relay = UdpRelayFactoryReal {}
+                .make(port, datagram_sub, Logger::new("test"))
+                .unwrap();
+            tx.send(()).unwrap();
+            system.run();
+        });
+        rx.recv().unwrap();
+        let client = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let relay_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port);
+
+        client.send_to(b"outbound", relay_addr).unwrap();
+
+        awaiter.await_message_count(1);
+        let recording = recording_arc.lock().unwrap();
+        assert_eq!(
+            recording.get_record::<InboundClientDatagram>(0),
+            &InboundClientDatagram {
+                peer_addr: client.local_addr().unwrap(),
+                data: b"outbound".to_vec(),
+            }
+        );
+    }
+
+    #[test]
+    fn udp_relay_sends_datagrams_to_clients() {
+        let port = find_free_port();
+        let (recorder, _, _) = make_recorder();
+        let client = UdpSocket::bind("127.0.0.1:0").unwrap();
+        client
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let client_addr = client.local_addr().unwrap();
+        let _system = System::new("udp_relay_sends_datagrams_to_clients");
+        let relay = UdpRelayFactoryReal {}
+            .make(
+                port,
+                recorder.start().recipient::<InboundClientDatagram>(),
+                Logger::new("test"),
+            )
+            .unwrap();
+
+        let result = relay.send_to(b"inbound", client_addr);
+
+        assert_eq!(result.unwrap(), 7);
+        let mut buffer = [0u8; 100];
+        let (len, source) = client.recv_from(&mut buffer).unwrap();
+        assert_eq!(&buffer[..len], b"inbound");
+        assert_eq!(source.port(), port);
+    }
+}
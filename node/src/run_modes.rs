@@ -1,14 +1,22 @@
 // Copyright (c) 2017-2019, Substratum LLC (https://substratum.net) and/or its affiliates. All rights reserved.
 
+use crate::blockchain::balance_history;
+use crate::blockchain::faucet;
 use crate::daemon::daemon_initializer::{DaemonInitializer, RecipientsFactoryReal, RerunnerReal};
 use crate::daemon::ChannelFactoryReal;
 use crate::database::config_dumper;
+use crate::diagnostics;
+use crate::neighborhood::gossip_recording;
 use crate::node_configurator::node_configurator_generate_wallet::NodeConfiguratorGenerateWallet;
 use crate::node_configurator::node_configurator_initialization::NodeConfiguratorInitialization;
 use crate::node_configurator::node_configurator_recover_wallet::NodeConfiguratorRecoverWallet;
 use crate::node_configurator::{NodeConfigurator, RealDirsWrapper, WalletCreationConfig};
 use crate::privilege_drop::{PrivilegeDropper, PrivilegeDropperReal};
+use crate::self_test;
 use crate::server_initializer::{LoggerInitializerWrapperReal, ServerInitializer};
+use crate::service_installer;
+use crate::sub_lib::cli_output;
+use crate::sub_lib::cli_output::OutputFormat;
 use actix::System;
 use futures::future::Future;
 use masq_lib::command::{Command, StdStreams};
@@ -19,6 +27,13 @@ enum Mode {
     GenerateWallet,
     RecoverWallet,
     DumpConfig,
+    CollectDiagnostics,
+    SelfTest,
+    ReplayGossip,
+    RequestTestFunds,
+    ExportBalanceHistory,
+    InstallService,
+    UninstallService,
     Initialization,
     Service,
 }
@@ -61,12 +76,29 @@ impl RunModes {
             Mode::GenerateWallet => self.generate_wallet(args, streams),
             Mode::RecoverWallet => self.recover_wallet(args, streams),
             Mode::DumpConfig => self.runner.dump_config(args, streams),
+            Mode::CollectDiagnostics => self.runner.collect_diagnostics(args, streams),
+            Mode::SelfTest => self.runner.self_test(args, streams),
+            Mode::ReplayGossip => self.runner.replay_gossip(args, streams),
+            Mode::RequestTestFunds => self.runner.request_test_funds(args, streams),
+            Mode::ExportBalanceHistory => self.runner.export_balance_history(args, streams),
+            Mode::InstallService => self.runner.install_service(args, streams),
+            Mode::UninstallService => self.runner.uninstall_service(args, streams),
             Mode::Initialization => self.runner.initialization(args, streams),
             Mode::Service => self.runner.run_service(args, streams),
         } {
             Ok(exit_code) => exit_code,
             Err(e) => {
+                if let (Some(mode_name), OutputFormat::Json) =
+                    (Self::output_mode_name(&mode), OutputFormat::from_args(args))
+                {
+                    cli_output::write(
+                        streams.stdout,
+                        &cli_output::configuration_failure(mode_name, &e),
+                    );
+                    return e.exit_code();
+                }
                 writeln!(streams.stderr, "Configuration error").expect("writeln! error");
+                let exit_code = e.exit_code();
                 e.param_errors.into_iter().for_each(|required| {
                     writeln!(
                         streams.stderr,
@@ -75,11 +107,24 @@ impl RunModes {
                     )
                     .expect("writeln! error")
                 });
-                1
+                exit_code
             }
         }
     }
 
+    // What the modes that can answer with --output json call themselves there
+    fn output_mode_name(mode: &Mode) -> Option<&'static str> {
+        match mode {
+            Mode::GenerateWallet => Some("generateWallet"),
+            Mode::RecoverWallet => Some("recoverWallet"),
+            Mode::DumpConfig => Some("dumpConfig"),
+            Mode::CollectDiagnostics => Some("collectDiagnostics"),
+            Mode::SelfTest => Some("selfTest"),
+            Mode::RequestTestFunds => Some("requestTestFunds"),
+            _ => None,
+        }
+    }
+
     fn args_contain_help_or_version(args: &[String]) -> bool {
         args.contains(&"--help".to_string())
             || args.contains(&"-h".to_string())
@@ -87,6 +132,11 @@ impl RunModes {
             || args.contains(&"-V".to_string())
     }
 
+    // A dry run only shows what would be done, so anybody can do it
+    fn args_contain_dry_run(args: &[String]) -> bool {
+        args.contains(&service_installer::DRY_RUN_FLAG.to_string())
+    }
+
     #[cfg(not(target_os = "windows"))]
     fn privilege_mismatch_message(mode: &Mode, need_but_dont_have: bool) -> String {
         let (requirement, recommendation) = if need_but_dont_have {
@@ -113,6 +163,21 @@ impl RunModes {
     fn determine_mode_and_priv_req(&self, args: &[String]) -> (Mode, bool) {
         if args.contains(&"--dump-config".to_string()) {
             (Mode::DumpConfig, false)
+        } else if args.contains(&"--collect-diagnostics".to_string()) {
+            (Mode::CollectDiagnostics, false)
+        } else if args.contains(&self_test::SELF_TEST_FLAG.to_string()) {
+            // The ports have to be checked with the privilege that will bind them
+            (Mode::SelfTest, true)
+        } else if args.contains(&gossip_recording::REPLAY_GOSSIP_FLAG.to_string()) {
+            (Mode::ReplayGossip, false)
+        } else if args.contains(&faucet::REQUEST_TEST_FUNDS_FLAG.to_string()) {
+            (Mode::RequestTestFunds, false)
+        } else if args.contains(&balance_history::EXPORT_BALANCE_HISTORY_FLAG.to_string()) {
+            (Mode::ExportBalanceHistory, false)
+        } else if args.contains(&service_installer::INSTALL_SERVICE_FLAG.to_string()) {
+            (Mode::InstallService, !Self::args_contain_dry_run(args))
+        } else if args.contains(&service_installer::UNINSTALL_SERVICE_FLAG.to_string()) {
+            (Mode::UninstallService, !Self::args_contain_dry_run(args))
         } else if args.contains(&"--recover-wallet".to_string()) {
             (Mode::RecoverWallet, false)
         } else if args.contains(&"--generate-wallet".to_string()) {
@@ -164,6 +229,41 @@ trait Runner {
         args: &[String],
         streams: &mut StdStreams<'_>,
     ) -> Result<i32, ConfiguratorError>;
+    fn collect_diagnostics(
+        &self,
+        args: &[String],
+        streams: &mut StdStreams<'_>,
+    ) -> Result<i32, ConfiguratorError>;
+    fn self_test(
+        &self,
+        args: &[String],
+        streams: &mut StdStreams<'_>,
+    ) -> Result<i32, ConfiguratorError>;
+    fn replay_gossip(
+        &self,
+        args: &[String],
+        streams: &mut StdStreams<'_>,
+    ) -> Result<i32, ConfiguratorError>;
+    fn request_test_funds(
+        &self,
+        args: &[String],
+        streams: &mut StdStreams<'_>,
+    ) -> Result<i32, ConfiguratorError>;
+    fn export_balance_history(
+        &self,
+        args: &[String],
+        streams: &mut StdStreams<'_>,
+    ) -> Result<i32, ConfiguratorError>;
+    fn install_service(
+        &self,
+        args: &[String],
+        streams: &mut StdStreams<'_>,
+    ) -> Result<i32, ConfiguratorError>;
+    fn uninstall_service(
+        &self,
+        args: &[String],
+        streams: &mut StdStreams<'_>,
+    ) -> Result<i32, ConfiguratorError>;
     fn initialization(
         &self,
         args: &[String],
@@ -206,11 +306,73 @@ impl Runner for RunnerReal {
         config_dumper::dump_config(args, streams)
     }
 
+    fn collect_diagnostics(
+        &self,
+        args: &[String],
+        streams: &mut StdStreams<'_>,
+    ) -> Result<i32, ConfiguratorError> {
+        diagnostics::collect_diagnostics(args, streams)
+    }
+
+    fn self_test(
+        &self,
+        args: &[String],
+        streams: &mut StdStreams<'_>,
+    ) -> Result<i32, ConfiguratorError> {
+        self_test::self_test(args, streams)
+    }
+
+    fn replay_gossip(
+        &self,
+        args: &[String],
+        streams: &mut StdStreams<'_>,
+    ) -> Result<i32, ConfiguratorError> {
+        gossip_recording::replay_gossip(args, streams)
+    }
+
+    fn request_test_funds(
+        &self,
+        args: &[String],
+        streams: &mut StdStreams<'_>,
+    ) -> Result<i32, ConfiguratorError> {
+        faucet::request_test_funds_mode(args, streams)
+    }
+
+    fn export_balance_history(
+        &self,
+        args: &[String],
+        streams: &mut StdStreams<'_>,
+    ) -> Result<i32, ConfiguratorError> {
+        balance_history::export_balance_history_mode(args, streams)
+    }
+
+    fn install_service(
+        &self,
+        args: &[String],
+        streams: &mut StdStreams<'_>,
+    ) -> Result<i32, ConfiguratorError> {
+        service_installer::install_service(args, streams)
+    }
+
+    fn uninstall_service(
+        &self,
+        args: &[String],
+        streams: &mut StdStreams<'_>,
+    ) -> Result<i32, ConfiguratorError> {
+        service_installer::uninstall_service(args, streams)
+    }
+
     fn initialization(
         &self,
         args: &[String],
         streams: &mut StdStreams<'_>,
     ) -> Result<i32, ConfiguratorError> {
+        #[cfg(target_os = "windows")]
+        {
+            if args.contains(&service_installer::WINDOWS_SERVICE_FLAG.to_string()) {
+                return Ok(service_installer::run_as_windows_service());
+            }
+        }
         let configurator = NodeConfiguratorInitialization {};
         let config = configurator.configure(args, streams)?;
         let mut initializer = DaemonInitializer::new(
@@ -249,6 +411,7 @@ impl RunnerReal {
 mod tests {
     use super::*;
     use crate::server_initializer::test_utils::PrivilegeDropperMock;
+    use masq_lib::shared_schema::CONFIGURATION_FAILURE_EXIT_CODE;
     use masq_lib::test_utils::fake_stream_holder::FakeStreamHolder;
     use std::cell::RefCell;
     use std::sync::{Arc, Mutex};
@@ -258,6 +421,20 @@ mod tests {
         run_service_results: RefCell<Vec<Result<i32, ConfiguratorError>>>,
         dump_config_params: Arc<Mutex<Vec<Vec<String>>>>,
         dump_config_results: RefCell<Vec<Result<i32, ConfiguratorError>>>,
+        collect_diagnostics_params: Arc<Mutex<Vec<Vec<String>>>>,
+        collect_diagnostics_results: RefCell<Vec<Result<i32, ConfiguratorError>>>,
+        self_test_params: Arc<Mutex<Vec<Vec<String>>>>,
+        self_test_results: RefCell<Vec<Result<i32, ConfiguratorError>>>,
+        replay_gossip_params: Arc<Mutex<Vec<Vec<String>>>>,
+        replay_gossip_results: RefCell<Vec<Result<i32, ConfiguratorError>>>,
+        request_test_funds_params: Arc<Mutex<Vec<Vec<String>>>>,
+        request_test_funds_results: RefCell<Vec<Result<i32, ConfiguratorError>>>,
+        export_balance_history_params: Arc<Mutex<Vec<Vec<String>>>>,
+        export_balance_history_results: RefCell<Vec<Result<i32, ConfiguratorError>>>,
+        install_service_params: Arc<Mutex<Vec<Vec<String>>>>,
+        install_service_results: RefCell<Vec<Result<i32, ConfiguratorError>>>,
+        uninstall_service_params: Arc<Mutex<Vec<Vec<String>>>>,
+        uninstall_service_results: RefCell<Vec<Result<i32, ConfiguratorError>>>,
         initialization_params: Arc<Mutex<Vec<Vec<String>>>>,
         initialization_results: RefCell<Vec<Result<i32, ConfiguratorError>>>,
         configuration_run_params: Arc<Mutex<Vec<Vec<String>>>>,
@@ -283,6 +460,87 @@ mod tests {
             self.dump_config_results.borrow_mut().remove(0)
         }
 
+        fn collect_diagnostics(
+            &self,
+            args: &[String],
+            _streams: &mut StdStreams<'_>,
+        ) -> Result<i32, ConfiguratorError> {
+            self.collect_diagnostics_params
+                .lock()
+                .unwrap()
+                .push(args.to_vec());
+            self.collect_diagnostics_results.borrow_mut().remove(0)
+        }
+
+        fn self_test(
+            &self,
+            args: &[String],
+            _streams: &mut StdStreams<'_>,
+        ) -> Result<i32, ConfiguratorError> {
+            self.self_test_params.lock().unwrap().push(args.to_vec());
+            self.self_test_results.borrow_mut().remove(0)
+        }
+
+        fn replay_gossip(
+            &self,
+            args: &[String],
+            _streams: &mut StdStreams<'_>,
+        ) -> Result<i32, ConfiguratorError> {
+            self.replay_gossip_params
+                .lock()
+                .unwrap()
+                .push(args.to_vec());
+            self.replay_gossip_results.borrow_mut().remove(0)
+        }
+
+        fn request_test_funds(
+            &self,
+            args: &[String],
+            _streams: &mut StdStreams<'_>,
+        ) -> Result<i32, ConfiguratorError> {
+            self.request_test_funds_params
+                .lock()
+                .unwrap()
+                .push(args.to_vec());
+            self.request_test_funds_results.borrow_mut().remove(0)
+        }
+
+        fn export_balance_history(
+            &self,
+            args: &[String],
+            _streams: &mut StdStreams<'_>,
+        ) -> Result<i32, ConfiguratorError> {
+            self.export_balance_history_params
+                .lock()
+                .unwrap()
+                .push(args.to_vec());
+            self.export_balance_history_results.borrow_mut().remove(0)
+        }
+
+        fn install_service(
+            &self,
+            args: &[String],
+            _streams: &mut StdStreams<'_>,
+        ) -> Result<i32, ConfiguratorError> {
+            self.install_service_params
+                .lock()
+                .unwrap()
+                .push(args.to_vec());
+            self.install_service_results.borrow_mut().remove(0)
+        }
+
+        fn uninstall_service(
+            &self,
+            args: &[String],
+            _streams: &mut StdStreams<'_>,
+        ) -> Result<i32, ConfiguratorError> {
+            self.uninstall_service_params
+                .lock()
+                .unwrap()
+                .push(args.to_vec());
+            self.uninstall_service_results.borrow_mut().remove(0)
+        }
+
         fn initialization(
             &self,
             args: &[String],
@@ -318,6 +576,20 @@ mod tests {
                 run_service_results: RefCell::new(vec![]),
                 dump_config_params: Arc::new(Mutex::new(vec![])),
                 dump_config_results: RefCell::new(vec![]),
+                collect_diagnostics_params: Arc::new(Mutex::new(vec![])),
+                collect_diagnostics_results: RefCell::new(vec![]),
+                self_test_params: Arc::new(Mutex::new(vec![])),
+                self_test_results: RefCell::new(vec![]),
+                replay_gossip_params: Arc::new(Mutex::new(vec![])),
+                replay_gossip_results: RefCell::new(vec![]),
+                request_test_funds_params: Arc::new(Mutex::new(vec![])),
+                request_test_funds_results: RefCell::new(vec![]),
+                export_balance_history_params: Arc::new(Mutex::new(vec![])),
+                export_balance_history_results: RefCell::new(vec![]),
+                install_service_params: Arc::new(Mutex::new(vec![])),
+                install_service_results: RefCell::new(vec![]),
+                uninstall_service_params: Arc::new(Mutex::new(vec![])),
+                uninstall_service_results: RefCell::new(vec![]),
                 initialization_params: Arc::new(Mutex::new(vec![])),
                 initialization_results: RefCell::new(vec![]),
                 configuration_run_params: Arc::new(Mutex::new(vec![])),
@@ -345,6 +617,81 @@ mod tests {
             self
         }
 
+        pub fn collect_diagnostics_params(mut self, params: &Arc<Mutex<Vec<Vec<String>>>>) -> Self {
+            self.collect_diagnostics_params = params.clone();
+            self
+        }
+
+        pub fn collect_diagnostics_result(self, result: Result<i32, ConfiguratorError>) -> Self {
+            self.collect_diagnostics_results.borrow_mut().push(result);
+            self
+        }
+
+        pub fn self_test_params(mut self, params: &Arc<Mutex<Vec<Vec<String>>>>) -> Self {
+            self.self_test_params = params.clone();
+            self
+        }
+
+        pub fn self_test_result(self, result: Result<i32, ConfiguratorError>) -> Self {
+            self.self_test_results.borrow_mut().push(result);
+            self
+        }
+
+        pub fn replay_gossip_params(mut self, params: &Arc<Mutex<Vec<Vec<String>>>>) -> Self {
+            self.replay_gossip_params = params.clone();
+            self
+        }
+
+        pub fn replay_gossip_result(self, result: Result<i32, ConfiguratorError>) -> Self {
+            self.replay_gossip_results.borrow_mut().push(result);
+            self
+        }
+
+        pub fn request_test_funds_params(mut self, params: &Arc<Mutex<Vec<Vec<String>>>>) -> Self {
+            self.request_test_funds_params = params.clone();
+            self
+        }
+
+        pub fn request_test_funds_result(self, result: Result<i32, ConfiguratorError>) -> Self {
+            self.request_test_funds_results.borrow_mut().push(result);
+            self
+        }
+
+        pub fn export_balance_history_params(
+            mut self,
+            params: &Arc<Mutex<Vec<Vec<String>>>>,
+        ) -> Self {
+            self.export_balance_history_params = params.clone();
+            self
+        }
+
+        pub fn export_balance_history_result(self, result: Result<i32, ConfiguratorError>) -> Self {
+            self.export_balance_history_results
+                .borrow_mut()
+                .push(result);
+            self
+        }
+
+        pub fn install_service_params(mut self, params: &Arc<Mutex<Vec<Vec<String>>>>) -> Self {
+            self.install_service_params = params.clone();
+            self
+        }
+
+        pub fn install_service_result(self, result: Result<i32, ConfiguratorError>) -> Self {
+            self.install_service_results.borrow_mut().push(result);
+            self
+        }
+
+        pub fn uninstall_service_params(mut self, params: &Arc<Mutex<Vec<Vec<String>>>>) -> Self {
+            self.uninstall_service_params = params.clone();
+            self
+        }
+
+        pub fn uninstall_service_result(self, result: Result<i32, ConfiguratorError>) -> Self {
+            self.uninstall_service_results.borrow_mut().push(result);
+            self
+        }
+
         pub fn initialization_params(mut self, params: &Arc<Mutex<Vec<Vec<String>>>>) -> Self {
             self.initialization_params = params.clone();
             self
@@ -387,6 +734,75 @@ mod tests {
             .for_each(|args| check_mode(args, Mode::DumpConfig, false));
     }
 
+    #[test]
+    fn collect_diagnostics() {
+        [["--collect-diagnostics"]]
+            .iter()
+            .for_each(|args| check_mode(args, Mode::CollectDiagnostics, false));
+    }
+
+    #[test]
+    fn self_test() {
+        check_mode(&["--self-test"], Mode::SelfTest, true);
+        check_mode(&["--initialization", "--self-test"], Mode::SelfTest, true);
+    }
+
+    #[test]
+    fn replay_gossip() {
+        check_mode(
+            &["--replay-gossip", "gossip-recording.cbor"],
+            Mode::ReplayGossip,
+            false,
+        );
+    }
+
+    #[test]
+    fn request_test_funds() {
+        check_mode(
+            &[
+                "--request-test-funds",
+                "--faucet-url",
+                "https://faucet.example.com",
+            ],
+            Mode::RequestTestFunds,
+            false,
+        );
+    }
+
+    #[test]
+    fn export_balance_history() {
+        check_mode(
+            &["--export-balance-history", "--chain", "ropsten"],
+            Mode::ExportBalanceHistory,
+            false,
+        );
+    }
+
+    #[test]
+    fn install_service() {
+        check_mode(&["--install-service"], Mode::InstallService, true);
+        check_mode(
+            &["--install-service", "--dry-run"],
+            Mode::InstallService,
+            false,
+        );
+        check_mode(
+            &["--initialization", "--install-service"],
+            Mode::InstallService,
+            true,
+        );
+    }
+
+    #[test]
+    fn uninstall_service() {
+        check_mode(&["--uninstall-service"], Mode::UninstallService, true);
+        check_mode(
+            &["--uninstall-service", "--dry-run"],
+            Mode::UninstallService,
+            false,
+        );
+    }
+
     #[test]
     fn initialization() {
         [["--initialization"]]
@@ -510,6 +926,67 @@ parm2 - msg2\n"
         )
     }
 
+    #[test]
+    fn go_exits_with_the_failure_code_when_the_parameters_could_not_be_carried_out() {
+        let mut subject = RunModes::new();
+        subject.runner = Box::new(RunnerMock::new().dump_config_result(Err(
+            ConfiguratorError::failed(
+                "seed",
+                "Can't generate wallets: mnemonic seed has already been created",
+            ),
+        )));
+        subject.privilege_dropper =
+            Box::new(PrivilegeDropperMock::new().expect_privilege_result(true));
+        let mut holder = FakeStreamHolder::new();
+
+        let result = subject.go(&["--dump-config".to_string()], &mut holder.streams());
+
+        assert_eq!(result, CONFIGURATION_FAILURE_EXIT_CODE);
+        assert_eq!(
+            &holder.stderr.get_string(),
+            "Configuration error\n\
+seed - Can't generate wallets: mnemonic seed has already been created\n"
+        )
+    }
+
+    #[test]
+    fn go_renders_errors_as_json_when_asked() {
+        let mut subject = RunModes::new();
+        subject.runner = Box::new(
+            RunnerMock::new().dump_config_result(Err(ConfiguratorError::required("parm1", "msg1"))),
+        );
+        subject.privilege_dropper =
+            Box::new(PrivilegeDropperMock::new().expect_privilege_result(true));
+        let mut holder = FakeStreamHolder::new();
+
+        let result = subject.go(
+            &[
+                "--dump-config".to_string(),
+                "--output".to_string(),
+                "json".to_string(),
+            ],
+            &mut holder.streams(),
+        );
+
+        assert_eq!(result, 1);
+        assert_eq!(&holder.stderr.get_string(), "");
+        let output: serde_json::Value = serde_json::from_str(&holder.stdout.get_string()).unwrap();
+        assert_eq!(
+            output,
+            serde_json::json!({
+                "version": 1,
+                "mode": "dumpConfig",
+                "error": {
+                    "code": "configuration",
+                    "message": "Configuration error",
+                    "parameters": [
+                        {"parameter": "parm1", "code": "Invalid", "reason": "msg1"},
+                    ],
+                },
+            })
+        );
+    }
+
     #[test]
     fn initialization_and_service_modes_complain_without_privilege() {
         let mut subject = RunModes::new();
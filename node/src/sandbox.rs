@@ -0,0 +1,385 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+// Because we have conditional compilation going on in this file:
+#![allow(dead_code)]
+#![allow(unused_imports)]
+
+// Once its ports are bound and it's dropped to the real user, the Node needs no privilege of any
+// kind: it handles private keys and talks to strangers, so whatever it can no longer do can't be
+// turned against the machine if it's ever subverted. With --sandbox off, none of this happens.
+
+#[cfg(target_os = "linux")]
+use nix::libc;
+#[cfg(target_os = "windows")]
+use std::os::raw::c_void;
+
+#[cfg(target_os = "linux")]
+const PR_SET_NO_NEW_PRIVS: libc::c_int = 38;
+#[cfg(target_os = "linux")]
+const PR_CAPBSET_DROP: libc::c_int = 24;
+#[cfg(target_os = "linux")]
+const PR_CAP_AMBIENT: libc::c_int = 47;
+#[cfg(target_os = "linux")]
+const PR_CAP_AMBIENT_CLEAR_ALL: libc::c_ulong = 4;
+#[cfg(target_os = "linux")]
+const LINUX_CAPABILITY_VERSION_3: u32 = 0x2008_0522;
+// Higher than any capability the kernel knows of yet; dropping one it doesn't know fails harmlessly
+#[cfg(target_os = "linux")]
+const CAP_LAST_CAP: libc::c_ulong = 63;
+#[cfg(target_os = "linux")]
+const SECCOMP_SET_MODE_FILTER: libc::c_ulong = 1;
+// Without this, threads started before the filter went on would escape it
+#[cfg(target_os = "linux")]
+const SECCOMP_FILTER_FLAG_TSYNC: libc::c_ulong = 1;
+
+const BPF_LD_W_ABS: u16 = 0x20;
+const BPF_JMP_JEQ_K: u16 = 0x15;
+const BPF_JMP_JGE_K: u16 = 0x35;
+const BPF_RET_K: u16 = 0x06;
+const SECCOMP_RET_ALLOW: u32 = 0x7fff_0000;
+const SECCOMP_RET_ERRNO: u32 = 0x0005_0000;
+const EPERM: u32 = 1;
+// Where struct seccomp_data keeps the system call number and the architecture
+const SECCOMP_DATA_NR: u32 = 0;
+const SECCOMP_DATA_ARCH: u32 = 4;
+// On x86_64, system calls made through the x32 ABI have this bit set in their numbers
+const X32_SYSCALL_BIT: u32 = 0x4000_0000;
+
+#[cfg(target_arch = "x86_64")]
+const AUDIT_ARCH: u32 = 0xc000_003e;
+#[cfg(target_arch = "aarch64")]
+const AUDIT_ARCH: u32 = 0xc000_00b7;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SockFilter {
+    pub code: u16,
+    pub jt: u8,
+    pub jf: u8,
+    pub k: u32,
+}
+
+impl SockFilter {
+    fn statement(code: u16, k: u32) -> SockFilter {
+        SockFilter {
+            code,
+            jt: 0,
+            jf: 0,
+            k,
+        }
+    }
+
+    fn jump(code: u16, k: u32, jt: u8, jf: u8) -> SockFilter {
+        SockFilter { code, jt, jf, k }
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[repr(C)]
+struct SockFprog {
+    len: libc::c_ushort,
+    filter: *const SockFilter,
+}
+
+#[cfg(target_os = "linux")]
+#[repr(C)]
+struct CapUserHeader {
+    version: u32,
+    pid: libc::c_int,
+}
+
+#[cfg(target_os = "linux")]
+#[repr(C)]
+#[derive(Default)]
+struct CapUserData {
+    effective: u32,
+    permitted: u32,
+    inheritable: u32,
+}
+
+// Nothing a Node does after startup needs any of these; they're what an intruder would reach for
+// to look into other processes, change the system, or get privilege back
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+pub fn denied_syscalls() -> Vec<i64> {
+    let syscalls = vec![
+        libc::SYS_ptrace,
+        libc::SYS_process_vm_readv,
+        libc::SYS_process_vm_writev,
+        libc::SYS_kcmp,
+        libc::SYS_mount,
+        libc::SYS_umount2,
+        libc::SYS_pivot_root,
+        libc::SYS_chroot,
+        libc::SYS_unshare,
+        libc::SYS_setns,
+        libc::SYS_swapon,
+        libc::SYS_swapoff,
+        libc::SYS_reboot,
+        libc::SYS_kexec_load,
+        libc::SYS_init_module,
+        libc::SYS_finit_module,
+        libc::SYS_delete_module,
+        libc::SYS_acct,
+        libc::SYS_quotactl,
+        libc::SYS_settimeofday,
+        libc::SYS_clock_settime,
+        libc::SYS_clock_adjtime,
+        libc::SYS_adjtimex,
+        libc::SYS_sethostname,
+        libc::SYS_setdomainname,
+        libc::SYS_syslog,
+        libc::SYS_vhangup,
+        libc::SYS_bpf,
+        libc::SYS_perf_event_open,
+        libc::SYS_userfaultfd,
+        libc::SYS_open_by_handle_at,
+        libc::SYS_name_to_handle_at,
+        libc::SYS_keyctl,
+        libc::SYS_add_key,
+        libc::SYS_request_key,
+        libc::SYS_personality,
+        libc::SYS_setuid,
+        libc::SYS_setgid,
+        libc::SYS_setreuid,
+        libc::SYS_setregid,
+        libc::SYS_setresuid,
+        libc::SYS_setresgid,
+        libc::SYS_setfsuid,
+        libc::SYS_setfsgid,
+        libc::SYS_setgroups,
+        libc::SYS_capset,
+    ];
+    syscalls
+        .into_iter()
+        .chain(arch_denied_syscalls())
+        .map(|syscall| syscall as i64)
+        .collect()
+}
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+fn arch_denied_syscalls() -> Vec<libc::c_long> {
+    vec![libc::SYS_iopl, libc::SYS_ioperm, libc::SYS_uselib]
+}
+
+#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+fn arch_denied_syscalls() -> Vec<libc::c_long> {
+    vec![]
+}
+
+// A seccomp filter that fails each denied system call with EPERM and lets everything else through.
+// System calls made for any other architecture, or through the x32 ABI, are failed as well, so the
+// list can't be sidestepped by calling the same thing by another number.
+pub fn seccomp_program(audit_arch: u32, denied: &[i64]) -> Vec<SockFilter> {
+    let deny = SECCOMP_RET_ERRNO | EPERM;
+    let mut program = vec![
+        SockFilter::statement(BPF_LD_W_ABS, SECCOMP_DATA_ARCH),
+        SockFilter::jump(BPF_JMP_JEQ_K, audit_arch, 1, 0),
+        SockFilter::statement(BPF_RET_K, deny),
+        SockFilter::statement(BPF_LD_W_ABS, SECCOMP_DATA_NR),
+        SockFilter::jump(BPF_JMP_JGE_K, X32_SYSCALL_BIT, 0, 1),
+        SockFilter::statement(BPF_RET_K, deny),
+    ];
+    denied.iter().for_each(|syscall| {
+        program.push(SockFilter::jump(BPF_JMP_JEQ_K, *syscall as u32, 0, 1));
+        program.push(SockFilter::statement(BPF_RET_K, deny));
+    });
+    program.push(SockFilter::statement(BPF_RET_K, SECCOMP_RET_ALLOW));
+    program
+}
+
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+pub fn confine() -> Result<String, String> {
+    drop_capabilities()?;
+    if unsafe { libc::prctl(PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) } != 0 {
+        return Err(format!(
+            "couldn't forbid new privileges: {}",
+            std::io::Error::last_os_error()
+        ));
+    }
+    let program = seccomp_program(AUDIT_ARCH, &denied_syscalls());
+    let fprog = SockFprog {
+        len: program.len() as libc::c_ushort,
+        filter: program.as_ptr(),
+    };
+    let result = unsafe {
+        libc::syscall(
+            libc::SYS_seccomp,
+            SECCOMP_SET_MODE_FILTER,
+            SECCOMP_FILTER_FLAG_TSYNC,
+            &fprog as *const SockFprog,
+        )
+    };
+    if result != 0 {
+        return Err(format!(
+            "couldn't install seccomp filter: {}",
+            std::io::Error::last_os_error()
+        ));
+    }
+    Ok(format!(
+        "all capabilities dropped; {} system calls denied",
+        denied_syscalls().len()
+    ))
+}
+
+// A Node started by an unprivileged user with file capabilities (setcap) still has them after
+// dropping privileges, since there's no root to drop; they all go here. The bounding set can only
+// be emptied with CAP_SETPCAP, so a Node without it just leaves the bounding set alone.
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+fn drop_capabilities() -> Result<(), String> {
+    (0..=CAP_LAST_CAP).for_each(|capability| unsafe {
+        let _ = libc::prctl(PR_CAPBSET_DROP, capability, 0, 0, 0);
+    });
+    let _ = unsafe { libc::prctl(PR_CAP_AMBIENT, PR_CAP_AMBIENT_CLEAR_ALL, 0, 0, 0) };
+    let header = CapUserHeader {
+        version: LINUX_CAPABILITY_VERSION_3,
+        pid: 0,
+    };
+    let data = [CapUserData::default(), CapUserData::default()];
+    let result = unsafe {
+        libc::syscall(
+            libc::SYS_capset,
+            &header as *const CapUserHeader,
+            data.as_ptr(),
+        )
+    };
+    if result != 0 {
+        return Err(format!(
+            "couldn't drop capabilities: {}",
+            std::io::Error::last_os_error()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+const TOKEN_ADJUST_PRIVILEGES: u32 = 0x0020;
+#[cfg(target_os = "windows")]
+const TOKEN_QUERY: u32 = 0x0008;
+
+#[cfg(target_os = "windows")]
+#[link(name = "advapi32")]
+extern "system" {
+    fn OpenProcessToken(process: *mut c_void, access: u32, token: *mut *mut c_void) -> i32;
+    fn AdjustTokenPrivileges(
+        token: *mut c_void,
+        disable_all_privileges: i32,
+        new_state: *mut c_void,
+        buffer_length: u32,
+        previous_state: *mut c_void,
+        return_length: *mut u32,
+    ) -> i32;
+}
+
+#[cfg(target_os = "windows")]
+#[link(name = "kernel32")]
+extern "system" {
+    fn GetCurrentProcess() -> *mut c_void;
+    fn CloseHandle(handle: *mut c_void) -> i32;
+}
+
+// Windows runs the Node as administrator the whole way, so the best it can do is a token with
+// every privilege disabled: no debugging other processes, loading drivers, or taking ownership
+#[cfg(target_os = "windows")]
+pub fn confine() -> Result<String, String> {
+    let mut token: *mut c_void = std::ptr::null_mut();
+    if unsafe {
+        OpenProcessToken(
+            GetCurrentProcess(),
+            TOKEN_ADJUST_PRIVILEGES | TOKEN_QUERY,
+            &mut token,
+        )
+    } == 0
+    {
+        return Err(format!(
+            "couldn't open process token: {}",
+            std::io::Error::last_os_error()
+        ));
+    }
+    let result = unsafe {
+        AdjustTokenPrivileges(
+            token,
+            1,
+            std::ptr::null_mut(),
+            0,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    };
+    let error = std::io::Error::last_os_error();
+    unsafe { CloseHandle(token) };
+    if result == 0 {
+        return Err(format!("couldn't disable token privileges: {}", error));
+    }
+    Ok("all token privileges disabled".to_string())
+}
+
+#[cfg(not(any(
+    target_os = "windows",
+    all(
+        target_os = "linux",
+        any(target_arch = "x86_64", target_arch = "aarch64")
+    )
+)))]
+pub fn confine() -> Result<String, String> {
+    Err("not available on this platform".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seccomp_program_checks_the_architecture_then_denies_each_listed_call() {
+        let result = seccomp_program(0xc000_003e, &[101, 165]);
+
+        let deny = SockFilter::statement(BPF_RET_K, SECCOMP_RET_ERRNO | EPERM);
+        assert_eq!(
+            result,
+            vec![
+                SockFilter::statement(BPF_LD_W_ABS, SECCOMP_DATA_ARCH),
+                SockFilter::jump(BPF_JMP_JEQ_K, 0xc000_003e, 1, 0),
+                deny,
+                SockFilter::statement(BPF_LD_W_ABS, SECCOMP_DATA_NR),
+                SockFilter::jump(BPF_JMP_JGE_K, X32_SYSCALL_BIT, 0, 1),
+                deny,
+                SockFilter::jump(BPF_JMP_JEQ_K, 101, 0, 1),
+                deny,
+                SockFilter::jump(BPF_JMP_JEQ_K, 165, 0, 1),
+                deny,
+                SockFilter::statement(BPF_RET_K, SECCOMP_RET_ALLOW),
+            ]
+        );
+    }
+
+    #[cfg(all(
+        target_os = "linux",
+        any(target_arch = "x86_64", target_arch = "aarch64")
+    ))]
+    #[test]
+    fn denied_syscalls_leave_what_the_node_needs_alone() {
+        let result = denied_syscalls();
+
+        assert!(result.contains(&(libc::SYS_ptrace as i64)));
+        assert!(result.contains(&(libc::SYS_mount as i64)));
+        [
+            libc::SYS_read,
+            libc::SYS_write,
+            libc::SYS_socket,
+            libc::SYS_bind,
+            libc::SYS_connect,
+            libc::SYS_clone,
+            libc::SYS_execve,
+            libc::SYS_prctl,
+        ]
+        .iter()
+        .for_each(|syscall| assert!(!result.contains(&(*syscall as i64))));
+    }
+}
@@ -0,0 +1,773 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+
+use crate::blockchain::blockchain_interface::chain_name_from_id;
+use crate::bootstrapper::BootstrapperConfig;
+use crate::database::db_initializer::{DbInitializer, DbInitializerReal, InitializationError};
+use crate::db_config::password_budget::check_password_within_budget;
+use crate::db_config::persistent_configuration::{
+    PersistentConfigError, PersistentConfiguration, PersistentConfigurationReal,
+};
+use crate::node_configurator::node_configurator_standard::{app, standard};
+use crate::node_configurator::RealDirsWrapper;
+use crate::privilege_drop::{PrivilegeDropper, PrivilegeDropperReal};
+use crate::proxy_server::process_identifier::port_holder;
+use crate::sub_lib::cli_output;
+use crate::sub_lib::cli_output::OutputFormat;
+use clap::{value_t, Arg};
+use futures::Future;
+use masq_lib::command::StdStreams;
+use masq_lib::multi_config::MultiConfig;
+use masq_lib::shared_schema::{output_arg, ConfiguratorError};
+use serde_json::json;
+use std::io::ErrorKind;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener, UdpSocket};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use sysinfo::{DiskExt, SystemExt};
+use web3::transports::{EventLoopHandle, Http};
+use web3::types::{BlockId, BlockNumber};
+use web3::{Transport, Web3};
+
+pub const SELF_TEST_FLAG: &str = "--self-test";
+
+const SELF_TEST_HELP: &str =
+    "Check everything the Node needs before it can serve: that the database opens and the password \
+     decrypts it, that the blockchain service answers for the right chain, that the ports are free, \
+     that this computer's clock agrees with the blockchain's, that the neighbor descriptors parse, \
+     and that there's room on the disk. Prints what passed and what to do about what didn't, then \
+     exits without starting the Node.";
+
+// A few blocks' worth either way; more than that and one of the clocks is wrong
+const MAX_CLOCK_SKEW_SECS: u64 = 300;
+// Room for the database to grow and for a few days of logs
+const MIN_FREE_DISK_BYTES: u64 = 100_000_000;
+// Dev chains choose their own network IDs
+const DEV_CHAIN_ID: u8 = 2;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CheckReport {
+    pub check: &'static str,
+    pub passed: bool,
+    pub detail: String,
+    // What to do about a failure
+    pub hint_opt: Option<String>,
+}
+
+impl CheckReport {
+    fn pass(check: &'static str, detail: &str) -> CheckReport {
+        CheckReport {
+            check,
+            passed: true,
+            detail: detail.to_string(),
+            hint_opt: None,
+        }
+    }
+
+    fn fail(check: &'static str, detail: &str, hint: &str) -> CheckReport {
+        CheckReport {
+            check,
+            passed: false,
+            detail: detail.to_string(),
+            hint_opt: Some(hint.to_string()),
+        }
+    }
+}
+
+pub trait BlockchainProbe {
+    fn chain_id(&self) -> Result<u64, String>;
+    // Seconds since the epoch
+    fn latest_block_time(&self) -> Result<u64, String>;
+}
+
+pub struct BlockchainProbeReal {
+    _event_loop_handle: EventLoopHandle,
+    web3: Web3<Http>,
+}
+
+impl BlockchainProbe for BlockchainProbeReal {
+    fn chain_id(&self) -> Result<u64, String> {
+        let chain_id = self
+            .web3
+            .transport()
+            .execute("eth_chainId", vec![])
+            .wait()
+            .map_err(|e| format!("{:?}", e))?;
+        chain_id
+            .as_str()
+            .and_then(|hex| u64::from_str_radix(hex.trim_start_matches("0x"), 16).ok())
+            .ok_or_else(|| format!("it answered with chain ID {}", chain_id))
+    }
+
+    fn latest_block_time(&self) -> Result<u64, String> {
+        match self
+            .web3
+            .eth()
+            .block(BlockId::Number(BlockNumber::Latest))
+            .wait()
+        {
+            Ok(Some(block)) => Ok(block.timestamp.low_u64()),
+            Ok(None) => Err("it has no latest block".to_string()),
+            Err(e) => Err(format!("{:?}", e)),
+        }
+    }
+}
+
+impl BlockchainProbeReal {
+    pub fn new(url: &str) -> Result<BlockchainProbeReal, String> {
+        let (event_loop_handle, transport) = Http::new(url).map_err(|e| format!("{:?}", e))?;
+        Ok(BlockchainProbeReal {
+            _event_loop_handle: event_loop_handle,
+            web3: Web3::new(transport),
+        })
+    }
+}
+
+pub fn self_test(args: &[String], streams: &mut StdStreams) -> Result<i32, ConfiguratorError> {
+    let app = app()
+        .arg(
+            Arg::with_name("self-test")
+                .long("self-test")
+                .required(true)
+                .takes_value(false)
+                .help(SELF_TEST_HELP),
+        )
+        .arg(output_arg());
+    let dirs_wrapper = RealDirsWrapper {};
+    let multi_config =
+        standard::make_service_mode_multi_config(&dirs_wrapper, &app, args, streams)?;
+    let mut config = BootstrapperConfig::new();
+    standard::establish_port_configurations(&mut config);
+    standard::privileged_parse_args(&dirs_wrapper, &multi_config, &mut config, streams)?;
+    let reports = run_checks(&config, &multi_config, &PrivilegeDropperReal::new());
+    let passed = reports.iter().all(|report| report.passed);
+    match OutputFormat::from_args(args) {
+        OutputFormat::Human => {
+            write!(streams.stdout, "{}", render(&reports)).expect("write! failed")
+        }
+        OutputFormat::Json => cli_output::write(
+            streams.stdout,
+            &cli_output::success(
+                "selfTest",
+                json!({
+                    "passed": passed,
+                    "checks": reports.iter().map(|report| json!({
+                        "check": report.check,
+                        "passed": report.passed,
+                        "detail": report.detail,
+                        "hint": report.hint_opt,
+                    })).collect::<Vec<_>>(),
+                }),
+            ),
+        ),
+    }
+    Ok(if passed { 0 } else { 1 })
+}
+
+// Ports first, while the Node still has the privilege it will bind them with; then the rest as the
+// real user, so that nothing is left behind in the data directory belonging to root
+fn run_checks(
+    config: &BootstrapperConfig,
+    multi_config: &MultiConfig,
+    privilege_dropper: &dyn PrivilegeDropper,
+) -> Vec<CheckReport> {
+    let mut reports = vec![check_ports(&privileged_ports(config), config.dns_port)];
+    privilege_dropper.drop_privileges(&config.real_user);
+    let chain_id = config.blockchain_bridge_config.chain_id;
+    let (database_report, stored_clandestine_port_opt) = check_database(
+        &config.data_directory,
+        chain_id,
+        value_m!(multi_config, "db-password", String).as_deref(),
+    );
+    reports.push(database_report);
+    match value_m!(multi_config, "clandestine-port", u16).or(stored_clandestine_port_opt) {
+        Some(port) => reports.push(check_clandestine_port(port)),
+        None => reports.push(CheckReport::pass(
+            "clandestine port",
+            "The Node will choose a free one when it first starts",
+        )),
+    }
+    match &config.blockchain_bridge_config.blockchain_service_url {
+        Some(url) => match BlockchainProbeReal::new(url) {
+            Ok(probe) => {
+                reports.push(check_blockchain_service(&probe, url, chain_id));
+                reports.push(check_clock(&probe, now()));
+            }
+            Err(e) => reports.push(CheckReport::fail(
+                "blockchain service",
+                &format!("Couldn't use {} as a blockchain service: {}", url, e),
+                "Give --blockchain-service-url a URL like https://mainnet.infura.io/v3/<project>",
+            )),
+        },
+        None => {
+            reports.push(CheckReport::pass(
+                "blockchain service",
+                "No --blockchain-service-url was given, so the Node won't pay or see payments",
+            ));
+            reports.push(CheckReport::pass(
+                "clock",
+                "There's no blockchain service to compare this computer's clock with",
+            ));
+        }
+    }
+    reports.push(check_neighbor_descriptors(multi_config));
+    reports.push(check_disk_space(&config.data_directory, &disks()));
+    reports
+}
+
+fn privileged_ports(config: &BootstrapperConfig) -> Vec<u16> {
+    let mut ports = config
+        .port_configurations
+        .keys()
+        .cloned()
+        .collect::<Vec<u16>>();
+    ports.push(config.ui_gateway_config.ui_port);
+    ports.extend(config.metrics_port_opt);
+    ports.sort_unstable();
+    ports.dedup();
+    ports
+}
+
+fn check_ports(tcp_ports: &[u16], dns_port: u16) -> CheckReport {
+    let mut problems = tcp_ports
+        .iter()
+        .filter_map(|port| {
+            bind_problem(
+                TcpListener::bind(SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), *port))
+                    .map(|_| ()),
+                *port,
+            )
+        })
+        .collect::<Vec<String>>();
+    problems.extend(bind_problem(
+        UdpSocket::bind(SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), dns_port)).map(|_| ()),
+        dns_port,
+    ));
+    if problems.is_empty() {
+        CheckReport::pass(
+            "ports",
+            &format!(
+                "TCP ports {} and UDP port {} are free",
+                tcp_ports
+                    .iter()
+                    .map(|port| port.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", "),
+                dns_port
+            ),
+        )
+    } else {
+        CheckReport::fail(
+            "ports",
+            &problems.join("; "),
+            "Stop whatever is holding a port (on Linux, systemd-resolved often holds 53: see \
+             dns_utility), or run with sudo if the Node isn't allowed to bind it",
+        )
+    }
+}
+
+fn check_clandestine_port(port: u16) -> CheckReport {
+    match bind_problem(
+        TcpListener::bind(SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), port)).map(|_| ()),
+        port,
+    ) {
+        None => CheckReport::pass("clandestine port", &format!("Port {} is free", port)),
+        Some(problem) => CheckReport::fail(
+            "clandestine port",
+            &problem,
+            "Stop whatever is holding it, or choose another with --clandestine-port",
+        ),
+    }
+}
+
+fn bind_problem(result: std::io::Result<()>, port: u16) -> Option<String> {
+    match result {
+        Ok(()) => None,
+        Err(ref e) if e.kind() == ErrorKind::AddrInUse => Some(format!(
+            "Port {} is already in use by {}",
+            port,
+            port_holder(port).unwrap_or_else(|| "another program".to_string())
+        )),
+        Err(e) => Some(format!("Port {} can't be bound: {}", port, e)),
+    }
+}
+
+// Also says which clandestine port the database remembers, if it does
+fn check_database(
+    data_directory: &Path,
+    chain_id: u8,
+    db_password_opt: Option<&str>,
+) -> (CheckReport, Option<u16>) {
+    let conn =
+        match DbInitializerReal::new().initialize(&data_directory.to_path_buf(), chain_id, false) {
+            Ok(conn) => conn,
+            Err(InitializationError::Nonexistent) => {
+                return (
+                    CheckReport::pass(
+                        "database",
+                        "There's no database yet: the Node will create one when it starts",
+                    ),
+                    None,
+                )
+            }
+            Err(e) => return (
+                CheckReport::fail(
+                    "database",
+                    &format!(
+                        "The database in {} won't open: {:?}",
+                        data_directory.display(),
+                        e
+                    ),
+                    "Check that --data-directory and --chain are the ones this database was made \
+                     with, and that you own the directory",
+                ),
+                None,
+            ),
+        };
+    let mut persistent_config = PersistentConfigurationReal::from(conn);
+    let clandestine_port_opt = persistent_config.clandestine_port().unwrap_or(None);
+    let report = match (persistent_config.check_password(None), db_password_opt) {
+        (Ok(true), _) => {
+            CheckReport::pass("database", "The database opens; it has no password yet")
+        }
+        (Ok(false), None) => CheckReport::pass(
+            "database",
+            "The database opens; give --db-password to check that it decrypts too",
+        ),
+        // A guess here costs as much as a guess at startup, or this would be the way around the budget
+        (Ok(false), Some(db_password)) => match check_password_within_budget(
+            &mut persistent_config,
+            db_password,
+            SystemTime::now(),
+        ) {
+            Ok(true) => CheckReport::pass(
+                "database",
+                "The database opens and --db-password decrypts it",
+            ),
+            Ok(false) => CheckReport::fail(
+                "database",
+                "--db-password doesn't decrypt the database",
+                "Use the password you gave the first time you set one; it can't be recovered",
+            ),
+            Err(PersistentConfigError::PasswordCooldown(seconds)) => CheckReport::fail(
+                "database",
+                &format!(
+                    "Too many incorrect passwords; --db-password can't be tried for {} seconds",
+                    seconds
+                ),
+                "Wait, then use the password you gave the first time you set one",
+            ),
+            Err(e) => database_corrupt(e),
+        },
+        (Err(e), _) => database_corrupt(e),
+    };
+    (report, clandestine_port_opt)
+}
+
+fn database_corrupt<E: std::fmt::Debug>(e: E) -> CheckReport {
+    CheckReport::fail(
+        "database",
+        &format!(
+            "The database opens, but its configuration can't be read: {:?}",
+            e
+        ),
+        "Restore the database from a backup, or move it aside and let the Node make a new one",
+    )
+}
+
+fn check_blockchain_service(probe: &dyn BlockchainProbe, url: &str, chain_id: u8) -> CheckReport {
+    match probe.chain_id() {
+        Err(e) => CheckReport::fail(
+            "blockchain service",
+            &format!("Couldn't get an answer from {}: {}", url, e),
+            "Check --blockchain-service-url and this computer's connection to the internet",
+        ),
+        Ok(_) if chain_id == DEV_CHAIN_ID => {
+            CheckReport::pass("blockchain service", &format!("{} answers", url))
+        }
+        Ok(actual) if actual == chain_id as u64 => CheckReport::pass(
+            "blockchain service",
+            &format!("{} answers for {}", url, chain_name_from_id(chain_id)),
+        ),
+        Ok(actual) => CheckReport::fail(
+            "blockchain service",
+            &format!(
+                "{} is for chain ID {}, but this Node is on {} (chain ID {})",
+                url,
+                actual,
+                chain_name_from_id(chain_id),
+                chain_id
+            ),
+            &format!(
+                "Point --blockchain-service-url at a {} service, or change --chain",
+                chain_name_from_id(chain_id)
+            ),
+        ),
+    }
+}
+
+fn check_clock(probe: &dyn BlockchainProbe, now: u64) -> CheckReport {
+    match probe.latest_block_time() {
+        Err(e) => CheckReport::fail(
+            "clock",
+            &format!("Couldn't get the time of the latest block: {}", e),
+            "Check that the blockchain service is synced",
+        ),
+        Ok(block_time) if block_time > now + MAX_CLOCK_SKEW_SECS => CheckReport::fail(
+            "clock",
+            &format!(
+                "The latest block is {} seconds ahead of this computer's clock",
+                block_time - now
+            ),
+            "Set this computer's clock from network time",
+        ),
+        Ok(block_time) if now > block_time + MAX_CLOCK_SKEW_SECS => CheckReport::fail(
+            "clock",
+            &format!(
+                "The latest block is {} seconds behind this computer's clock",
+                now - block_time
+            ),
+            "Set this computer's clock from network time; if it's right, the blockchain service \
+             is behind",
+        ),
+        Ok(_) => CheckReport::pass(
+            "clock",
+            "This computer's clock agrees with the blockchain's",
+        ),
+    }
+}
+
+fn check_neighbor_descriptors(multi_config: &MultiConfig) -> CheckReport {
+    match standard::convert_ci_configs(multi_config) {
+        Ok(None) => CheckReport::pass("neighbor descriptors", "No --neighbors were given"),
+        Ok(Some(descriptors)) => CheckReport::pass(
+            "neighbor descriptors",
+            &format!("All {} descriptors in --neighbors parse", descriptors.len()),
+        ),
+        Err(e) => CheckReport::fail(
+            "neighbor descriptors",
+            &e.param_errors
+                .iter()
+                .map(|param_error| param_error.reason.clone())
+                .collect::<Vec<String>>()
+                .join("; "),
+            "Copy each descriptor exactly as its Node printed it: mainnet descriptors have '@' \
+             after the key, testnet descriptors ':'",
+        ),
+    }
+}
+
+// disks are mount points and the bytes free on each
+fn check_disk_space(data_directory: &Path, disks: &[(PathBuf, u64)]) -> CheckReport {
+    // The data directory may not have been made yet; the disk it will be on is the one that counts
+    let location = data_directory
+        .ancestors()
+        .find_map(|ancestor| ancestor.canonicalize().ok())
+        .unwrap_or_else(|| data_directory.to_path_buf());
+    let available_opt = disks
+        .iter()
+        .filter(|(mount_point, _)| location.starts_with(mount_point))
+        .max_by_key(|(mount_point, _)| mount_point.components().count())
+        .map(|(_, available)| *available);
+    match available_opt {
+        None => CheckReport::pass(
+            "disk space",
+            &format!(
+                "Couldn't tell which disk {} is on, so its space wasn't checked",
+                data_directory.display()
+            ),
+        ),
+        Some(available) if available < MIN_FREE_DISK_BYTES => CheckReport::fail(
+            "disk space",
+            &format!(
+                "Only {} MB is free for {}",
+                available / 1_000_000,
+                data_directory.display()
+            ),
+            &format!(
+                "Free up at least {} MB, or point --data-directory somewhere roomier",
+                MIN_FREE_DISK_BYTES / 1_000_000
+            ),
+        ),
+        Some(available) => CheckReport::pass(
+            "disk space",
+            &format!(
+                "{} MB is free for {}",
+                available / 1_000_000,
+                data_directory.display()
+            ),
+        ),
+    }
+}
+
+fn disks() -> Vec<(PathBuf, u64)> {
+    let mut system = sysinfo::System::new();
+    system.refresh_disks_list();
+    system
+        .get_disks()
+        .iter()
+        .map(|disk| {
+            (
+                disk.get_mount_point().to_path_buf(),
+                disk.get_available_space(),
+            )
+        })
+        .collect()
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since| since.as_secs())
+        .unwrap_or(0)
+}
+
+fn render(reports: &[CheckReport]) -> String {
+    let mut output = reports
+        .iter()
+        .map(|report| {
+            let mut line = format!(
+                "{}  {:<21} {}\n",
+                if report.passed { "PASS" } else { "FAIL" },
+                report.check,
+                report.detail
+            );
+            if let Some(hint) = &report.hint_opt {
+                line.push_str(&format!("      {:<21} {}\n", "", hint));
+            }
+            line
+        })
+        .collect::<String>();
+    let failures = reports.iter().filter(|report| !report.passed).count();
+    output.push_str(&match failures {
+        0 => "\nEverything passed: the Node is ready to start.\n".to_string(),
+        1 => "\n1 check failed.\n".to_string(),
+        n => format!("\n{} checks failed.\n", n),
+    });
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db_config::password_budget::FREE_PASSWORD_FAILURES;
+    use masq_lib::test_utils::utils::{ensure_node_home_directory_exists, DEFAULT_CHAIN_ID};
+
+    struct BlockchainProbeMock {
+        chain_id_result: Result<u64, String>,
+        latest_block_time_result: Result<u64, String>,
+    }
+
+    impl BlockchainProbe for BlockchainProbeMock {
+        fn chain_id(&self) -> Result<u64, String> {
+            self.chain_id_result.clone()
+        }
+
+        fn latest_block_time(&self) -> Result<u64, String> {
+            self.latest_block_time_result.clone()
+        }
+    }
+
+    fn probe(chain_id_result: Result<u64, String>, block_time: u64) -> BlockchainProbeMock {
+        BlockchainProbeMock {
+            chain_id_result,
+            latest_block_time_result: Ok(block_time),
+        }
+    }
+
+    #[test]
+    fn a_held_port_fails_with_its_holder() {
+        let listener = TcpListener::bind("0.0.0.0:0").unwrap();
+        let held_port = listener.local_addr().unwrap().port();
+        let free_port = TcpListener::bind("0.0.0.0:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let dns_port = UdpSocket::bind("0.0.0.0:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+
+        let result = check_ports(&[free_port, held_port], dns_port);
+
+        assert_eq!(result.passed, false);
+        assert!(
+            result
+                .detail
+                .starts_with(&format!("Port {} is already in use by ", held_port)),
+            "{}",
+            result.detail
+        );
+        assert!(
+            !result.detail.contains(&free_port.to_string()),
+            "{}",
+            result.detail
+        );
+    }
+
+    #[test]
+    fn a_missing_database_passes_and_a_wrong_password_fails() {
+        let home_dir = ensure_node_home_directory_exists(
+            "self_test",
+            "a_missing_database_passes_and_a_wrong_password_fails",
+        );
+
+        let (missing, _) = check_database(&home_dir, DEFAULT_CHAIN_ID, Some("password"));
+
+        assert_eq!(missing.passed, true);
+        let conn = DbInitializerReal::new()
+            .initialize(&home_dir, DEFAULT_CHAIN_ID, true)
+            .unwrap();
+        let mut persistent_config = PersistentConfigurationReal::from(conn);
+        persistent_config.change_password(None, "password").unwrap();
+        persistent_config.set_clandestine_port(4567).unwrap();
+        let (right, clandestine_port_opt) =
+            check_database(&home_dir, DEFAULT_CHAIN_ID, Some("password"));
+        let (wrong, _) = check_database(&home_dir, DEFAULT_CHAIN_ID, Some("booga"));
+        assert_eq!(right.passed, true);
+        assert_eq!(clandestine_port_opt, Some(4567));
+        assert_eq!(
+            wrong,
+            CheckReport::fail(
+                "database",
+                "--db-password doesn't decrypt the database",
+                "Use the password you gave the first time you set one; it can't be recovered",
+            )
+        );
+        assert_eq!(persistent_config.db_password_failures().unwrap().0, 1);
+    }
+
+    #[test]
+    fn wrong_passwords_spend_the_same_budget_as_they_do_at_startup() {
+        let home_dir = ensure_node_home_directory_exists(
+            "self_test",
+            "wrong_passwords_spend_the_same_budget_as_they_do_at_startup",
+        );
+        let conn = DbInitializerReal::new()
+            .initialize(&home_dir, DEFAULT_CHAIN_ID, true)
+            .unwrap();
+        let mut persistent_config = PersistentConfigurationReal::from(conn);
+        persistent_config.change_password(None, "password").unwrap();
+
+        (0..=FREE_PASSWORD_FAILURES).for_each(|_| {
+            assert_eq!(
+                check_database(&home_dir, DEFAULT_CHAIN_ID, Some("booga"))
+                    .0
+                    .passed,
+                false
+            )
+        });
+        let (right, _) = check_database(&home_dir, DEFAULT_CHAIN_ID, Some("password"));
+
+        assert_eq!(right.passed, false);
+        assert!(
+            right.detail.starts_with("Too many incorrect passwords;"),
+            "{}",
+            right.detail
+        );
+        assert_eq!(
+            persistent_config.db_password_failures().unwrap().0,
+            FREE_PASSWORD_FAILURES + 1
+        );
+    }
+
+    #[test]
+    fn a_blockchain_service_for_another_chain_fails() {
+        let result = check_blockchain_service(&probe(Ok(1), 0), "http://booga.com", 3);
+
+        assert_eq!(
+            result,
+            CheckReport::fail(
+                "blockchain service",
+                "http://booga.com is for chain ID 1, but this Node is on ropsten (chain ID 3)",
+                "Point --blockchain-service-url at a ropsten service, or change --chain",
+            )
+        );
+    }
+
+    #[test]
+    fn a_blockchain_service_that_does_not_answer_fails_and_a_dev_chain_is_not_held_to_its_id() {
+        let silent = check_blockchain_service(
+            &probe(Err("connection refused".to_string()), 0),
+            "http://booga.com",
+            3,
+        );
+        let dev = check_blockchain_service(&probe(Ok(1337), 0), "http://booga.com", 2);
+        let right = check_blockchain_service(&probe(Ok(3), 0), "http://booga.com", 3);
+
+        assert_eq!(silent.passed, false);
+        assert_eq!(
+            silent.detail,
+            "Couldn't get an answer from http://booga.com: connection refused"
+        );
+        assert_eq!(dev.passed, true);
+        assert_eq!(right.passed, true);
+    }
+
+    #[test]
+    fn clocks_more_than_a_few_blocks_apart_fail() {
+        let now = 1_000_000;
+
+        let close = check_clock(&probe(Ok(3), now - MAX_CLOCK_SKEW_SECS), now);
+        let ahead = check_clock(&probe(Ok(3), now + MAX_CLOCK_SKEW_SECS + 1), now);
+        let behind = check_clock(&probe(Ok(3), now - MAX_CLOCK_SKEW_SECS - 1), now);
+
+        assert_eq!(close.passed, true);
+        assert_eq!(
+            ahead.detail,
+            "The latest block is 301 seconds ahead of this computer's clock"
+        );
+        assert_eq!(ahead.passed, false);
+        assert_eq!(
+            behind.detail,
+            "The latest block is 301 seconds behind this computer's clock"
+        );
+        assert_eq!(behind.passed, false);
+    }
+
+    #[test]
+    fn disk_space_is_judged_by_the_deepest_mount_point_holding_the_data_directory() {
+        let home_dir = ensure_node_home_directory_exists(
+            "self_test",
+            "disk_space_is_judged_by_the_deepest_mount_point_holding_the_data_directory",
+        )
+        .canonicalize()
+        .unwrap();
+        let not_yet_made = home_dir.join("not").join("yet");
+        let roomy = vec![
+            (PathBuf::from("/"), 1),
+            (home_dir.clone(), MIN_FREE_DISK_BYTES),
+        ];
+        let cramped = vec![(PathBuf::from("/"), MIN_FREE_DISK_BYTES), (home_dir, 1)];
+
+        let roomy_result = check_disk_space(&not_yet_made, &roomy);
+        let cramped_result = check_disk_space(&not_yet_made, &cramped);
+
+        assert_eq!(roomy_result.passed, true);
+        assert_eq!(cramped_result.passed, false);
+        assert!(
+            cramped_result.detail.starts_with("Only 0 MB is free for "),
+            "{}",
+            cramped_result.detail
+        );
+    }
+
+    #[test]
+    fn render_gives_failures_their_hints_and_counts_them() {
+        let reports = vec![
+            CheckReport::pass("database", "Opens"),
+            CheckReport::fail("clock", "Wrong", "Fix it"),
+        ];
+
+        let result = render(&reports);
+
+        assert_eq!(
+            result,
+            "PASS  database              Opens\n\
+             FAIL  clock                 Wrong\n      \
+             \x20                     Fix it\n\
+             \n1 check failed.\n"
+        );
+    }
+}
@@ -8,10 +8,11 @@ use crate::node_configurator::node_configurator_standard::NodeConfiguratorStanda
 use crate::node_configurator::NodeConfigurator;
 use crate::node_configurator::RealDirsWrapper;
 use crate::sub_lib;
+use crate::sub_lib::log_control;
+use crate::sub_lib::log_control::LogLevels;
 use crate::sub_lib::socket_server::SocketServer;
 use backtrace::Backtrace;
 use chrono::{DateTime, Local};
-use flexi_logger::LogSpecBuilder;
 use flexi_logger::Logger;
 use flexi_logger::{Cleanup, Criterion, LevelFilter, Naming};
 use flexi_logger::{DeferredNow, Duplicate, Record};
@@ -67,6 +68,9 @@ impl Command for ServerInitializer {
             self.privilege_dropper
                 .chown(&config.data_directory, &real_user);
             self.privilege_dropper.drop_privileges(&real_user);
+            if config.sandbox {
+                Self::report_sandbox(self.privilege_dropper.sandbox());
+            }
 
             result = Self::combine_results(
                 result,
@@ -83,6 +87,7 @@ impl Command for ServerInitializer {
             1
         };
         if let Some(err) = result.err() {
+            let exit_code = err.exit_code() as u8;
             err.param_errors.into_iter().for_each(|param_error| {
                 writeln!(
                     streams.stderr,
@@ -91,7 +96,7 @@ impl Command for ServerInitializer {
                 )
                 .expect("writeln! failed")
             });
-            1
+            exit_code
         } else {
             exit_code
         }
@@ -121,6 +126,19 @@ impl ServerInitializer {
         }
     }
 
+    // A Node that can't be sandboxed is still better running than not
+    fn report_sandbox(result: Result<String, String>) {
+        let logger = sub_lib::logger::Logger::new("ServerInitializer");
+        match result {
+            Ok(summary) => info!(logger, "Sandboxed: {}", summary),
+            Err(e) => warning!(
+                logger,
+                "Couldn't sandbox the Node ({}); it will run unsandboxed",
+                e
+            ),
+        }
+    }
+
     fn combine_results<A: Debug, B: Debug>(
         initial: Result<A, ConfiguratorError>,
         additional: Result<B, ConfiguratorError>,
@@ -169,28 +187,27 @@ impl LoggerInitializerWrapper for LoggerInitializerWrapperReal {
         log_level: LevelFilter,
         discriminant_opt: Option<&str>,
     ) {
-        let mut logger = Logger::with(
-            LogSpecBuilder::new()
-                .default(log_level)
-                .module("tokio", LevelFilter::Off)
-                .module("mio", LevelFilter::Off)
-                .build(),
-        )
-        .log_to_file()
-        .directory(file_path.clone())
-        .print_message()
-        .duplicate_to_stderr(Duplicate::Info)
-        .suppress_timestamp()
-        .format(format_function)
-        .rotate(
-            Criterion::Size(100_000_000),
-            Naming::Numbers,
-            Cleanup::KeepZipFiles(50),
-        );
-        if let Some(discriminant) = discriminant_opt {
-            logger = logger.discriminant(discriminant);
-        }
-        logger.start().expect("Logging subsystem failed to start");
+        let levels = LogLevels::new(log_level);
+        let mut logger = Logger::with(levels.spec())
+            .log_to_file()
+            .directory(file_path.clone())
+            .print_message()
+            .duplicate_to_stderr(Duplicate::Info)
+            .suppress_timestamp()
+            .format(format_function);
+        logger = match discriminant_opt {
+            Some(discriminant) => logger.discriminant(discriminant).rotate(
+                Criterion::Size(100_000_000),
+                Naming::Numbers,
+                Cleanup::KeepZipFiles(50),
+            ),
+            // The Node's log is rotated by log_rotation, according to its persistent configuration
+            None => logger.rotate(Criterion::Size(u64::MAX), Naming::Numbers, Cleanup::Never),
+        };
+        let handle = logger.start().expect("Logging subsystem failed to start");
+        log_control::take_control(handle, levels);
+        #[cfg(not(target_os = "windows"))]
+        log_control::toggle_trace_on_sighup();
         let privilege_dropper = PrivilegeDropperReal::new();
         let logfile_name = file_path.join(format!(
             "MASQNode_{}rCURRENT.log",
@@ -323,6 +340,8 @@ pub mod test_utils {
         chown_params: Arc<Mutex<Vec<(PathBuf, RealUser)>>>,
         expect_privilege_params: Arc<Mutex<Vec<bool>>>,
         expect_privilege_results: RefCell<Vec<bool>>,
+        sandbox_params: Arc<Mutex<Vec<()>>>,
+        sandbox_results: RefCell<Vec<Result<String, String>>>,
     }
 
     impl PrivilegeDropper for PrivilegeDropperMock {
@@ -347,6 +366,16 @@ pub mod test_utils {
                 .push(privilege_expected);
             self.expect_privilege_results.borrow_mut().remove(0)
         }
+
+        fn sandbox(&self) -> Result<String, String> {
+            self.sandbox_params.lock().unwrap().push(());
+            let mut results = self.sandbox_results.borrow_mut();
+            if results.is_empty() {
+                Ok("nothing to do in a mock".to_string())
+            } else {
+                results.remove(0)
+            }
+        }
     }
 
     impl PrivilegeDropperMock {
@@ -356,9 +385,21 @@ pub mod test_utils {
                 chown_params: Arc::new(Mutex::new(vec![])),
                 expect_privilege_params: Arc::new(Mutex::new(vec![])),
                 expect_privilege_results: RefCell::new(vec![]),
+                sandbox_params: Arc::new(Mutex::new(vec![])),
+                sandbox_results: RefCell::new(vec![]),
             }
         }
 
+        pub fn sandbox_params(mut self, params: &Arc<Mutex<Vec<()>>>) -> Self {
+            self.sandbox_params = params.clone();
+            self
+        }
+
+        pub fn sandbox_result(self, result: Result<String, String>) -> Self {
+            self.sandbox_results.borrow_mut().push(result);
+            self
+        }
+
         pub fn drop_privileges_params(mut self, params: &Arc<Mutex<Vec<RealUser>>>) -> Self {
             self.drop_privileges_params = params.clone();
             self
@@ -808,6 +849,60 @@ pub mod tests {
         assert_eq!(*drop_privileges_params, vec![real_user]);
     }
 
+    fn go_with_sandbox(sandbox: bool, privilege_dropper: PrivilegeDropperMock) {
+        let _ = LogfileNameGuard::new(&PathBuf::from("uninitialized"));
+        let mut bootstrapper_config = BootstrapperConfig::new();
+        bootstrapper_config.sandbox = sandbox;
+        let mut subject = ServerInitializer {
+            dns_socket_server: Box::new(CrashTestDummy::new(CrashPoint::None, ())),
+            bootstrapper: Box::new(CrashTestDummy::new(CrashPoint::None, bootstrapper_config)),
+            privilege_dropper: Box::new(privilege_dropper),
+        };
+
+        subject.go(&mut FakeStreamHolder::new().streams(), &[]);
+    }
+
+    #[test]
+    fn go_should_sandbox_after_dropping_privileges() {
+        init_test_logging();
+        let sandbox_params_arc = Arc::new(Mutex::new(vec![]));
+        let privilege_dropper = PrivilegeDropperMock::new()
+            .sandbox_params(&sandbox_params_arc)
+            .sandbox_result(Ok(
+                "all capabilities dropped; 49 system calls denied".to_string()
+            ));
+
+        go_with_sandbox(true, privilege_dropper);
+
+        assert_eq!(sandbox_params_arc.lock().unwrap().len(), 1);
+        TestLogHandler::new().exists_log_containing(
+            "INFO: ServerInitializer: Sandboxed: all capabilities dropped; 49 system calls denied",
+        );
+    }
+
+    #[test]
+    fn go_should_run_unsandboxed_with_a_warning_if_the_sandbox_fails() {
+        init_test_logging();
+        let privilege_dropper = PrivilegeDropperMock::new()
+            .sandbox_result(Err("not available on this platform".to_string()));
+
+        go_with_sandbox(true, privilege_dropper);
+
+        TestLogHandler::new().exists_log_containing(
+            "WARN: ServerInitializer: Couldn't sandbox the Node (not available on this platform); it will run unsandboxed",
+        );
+    }
+
+    #[test]
+    fn go_should_not_sandbox_with_sandbox_off() {
+        let sandbox_params_arc = Arc::new(Mutex::new(vec![]));
+        let privilege_dropper = PrivilegeDropperMock::new().sandbox_params(&sandbox_params_arc);
+
+        go_with_sandbox(false, privilege_dropper);
+
+        assert_eq!(sandbox_params_arc.lock().unwrap().len(), 0);
+    }
+
     #[test]
     #[should_panic(expected = "0: ")]
     fn go_with_help_should_print_help_and_artificially_panic() {
@@ -0,0 +1,589 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+
+#[cfg(target_os = "linux")]
+use crate::bootstrapper::RealUser;
+use crate::node_configurator::node_configurator_initialization::NodeConfiguratorInitialization;
+use crate::node_configurator::NodeConfigurator;
+#[cfg(target_os = "linux")]
+use crate::node_configurator::RealDirsWrapper;
+use masq_lib::command::StdStreams;
+use masq_lib::shared_schema::ConfiguratorError;
+#[cfg(target_os = "linux")]
+use std::str::FromStr;
+
+pub const INSTALL_SERVICE_FLAG: &str = "--install-service";
+pub const UNINSTALL_SERVICE_FLAG: &str = "--uninstall-service";
+pub const DRY_RUN_FLAG: &str = "--dry-run";
+// Tells a Daemon that the Windows Service Control Manager started it
+#[cfg(target_os = "windows")]
+pub const WINDOWS_SERVICE_FLAG: &str = "--windows-service";
+
+// What --install-service was asked to do. Everything it doesn't recognize is for the Daemon.
+#[derive(Debug, PartialEq)]
+pub struct ServiceRequest {
+    pub daemon_args: Vec<String>,
+    pub real_user_opt: Option<String>,
+    pub dry_run: bool,
+}
+
+impl ServiceRequest {
+    pub fn from_args(args: &[String]) -> ServiceRequest {
+        let mut request = ServiceRequest {
+            daemon_args: vec![],
+            real_user_opt: None,
+            dry_run: false,
+        };
+        let mut remaining = args.iter().skip(1).peekable();
+        while let Some(arg) = remaining.next() {
+            match arg.as_str() {
+                INSTALL_SERVICE_FLAG | UNINSTALL_SERVICE_FLAG => (),
+                DRY_RUN_FLAG => request.dry_run = true,
+                "--real-user" => {
+                    request.real_user_opt = match remaining.peek() {
+                        Some(value) if !value.starts_with("--") => remaining.next().cloned(),
+                        _ => None,
+                    }
+                }
+                _ => request.daemon_args.push(arg.clone()),
+            }
+        }
+        request
+    }
+
+    // The Daemon's own parameters are checked now, rather than when the service first starts
+    fn validate(&self, streams: &mut StdStreams) -> Result<(), ConfiguratorError> {
+        let mut args = vec!["MASQNode".to_string(), "--initialization".to_string()];
+        args.extend(self.daemon_args.iter().cloned());
+        NodeConfiguratorInitialization {}
+            .configure(&args, streams)
+            .map(|_| ())
+    }
+
+    // Only systemd needs to be told; Windows services run as LocalSystem
+    #[cfg(target_os = "linux")]
+    fn real_user(&self) -> Result<RealUser, ConfiguratorError> {
+        let real_user = match &self.real_user_opt {
+            Some(real_user_str) => RealUser::from_str(real_user_str).map_err(|_| {
+                ConfiguratorError::required("real-user", "Should look like <uid>:<gid>:<home-dir>")
+            })?,
+            None => RealUser::new(None, None, None),
+        };
+        // Nodes must never run as root, so root can't be the real user
+        let real_user = real_user.populate(&RealDirsWrapper {});
+        if real_user.uid_opt == Some(0) {
+            return Err(ConfiguratorError::required(
+                "real-user",
+                "Can't tell whose Nodes the service should run; use sudo, or specify --real-user",
+            ));
+        }
+        Ok(real_user)
+    }
+}
+
+pub fn install_service(
+    args: &[String],
+    streams: &mut StdStreams,
+) -> Result<i32, ConfiguratorError> {
+    let request = ServiceRequest::from_args(args);
+    request.validate(streams)?;
+    platform::install(request, streams)
+}
+
+pub fn uninstall_service(
+    args: &[String],
+    streams: &mut StdStreams,
+) -> Result<i32, ConfiguratorError> {
+    let request = ServiceRequest::from_args(args);
+    platform::uninstall(request, streams)
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::*;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
+
+    const UNIT_NAME: &str = "masq-daemon.service";
+    const UNIT_DIRECTORY: &str = "/etc/systemd/system";
+
+    // The Daemon runs as root so that the Nodes it starts can open low ports and then drop to the
+    // real user; the bounding set holds every privilege either of them uses, and nothing else.
+    // The home directories stay writable because a Node's data directory can be anywhere.
+    const SERVICE_HARDENING: &str = "\
+CapabilityBoundingSet=CAP_NET_BIND_SERVICE CAP_SETUID CAP_SETGID CAP_CHOWN CAP_DAC_OVERRIDE CAP_FOWNER CAP_KILL CAP_SYS_RESOURCE
+NoNewPrivileges=true
+PrivateTmp=true
+ProtectSystem=full
+ProtectKernelTunables=true
+ProtectKernelModules=true
+ProtectKernelLogs=true
+ProtectControlGroups=true
+RestrictNamespaces=true
+RestrictRealtime=true
+RestrictSUIDSGID=true
+RestrictAddressFamilies=AF_UNIX AF_INET AF_INET6 AF_NETLINK
+LockPersonality=true
+SystemCallArchitectures=native
+";
+
+    pub fn install(
+        request: ServiceRequest,
+        streams: &mut StdStreams,
+    ) -> Result<i32, ConfiguratorError> {
+        let real_user = request.real_user()?;
+        let executable = std::env::current_exe().expect("Can't find MASQNode executable");
+        let unit = systemd_unit(&executable, &request.daemon_args, &real_user);
+        if request.dry_run {
+            write!(streams.stdout, "{}", unit).expect("write! failed");
+            return Ok(0);
+        }
+        let unit_path = unit_path();
+        let result = fs::write(&unit_path, unit)
+            .and_then(|_| fs::set_permissions(&unit_path, fs::Permissions::from_mode(0o644)))
+            .map_err(|e| format!("Couldn't write {}: {}", unit_path.display(), e))
+            .and_then(|_| systemctl(&["daemon-reload"]))
+            .and_then(|_| systemctl(&["enable", "--now", UNIT_NAME]));
+        Ok(report(
+            result,
+            &format!("Installed and started {}", unit_path.display()),
+            streams,
+        ))
+    }
+
+    pub fn uninstall(
+        request: ServiceRequest,
+        streams: &mut StdStreams,
+    ) -> Result<i32, ConfiguratorError> {
+        let unit_path = unit_path();
+        if request.dry_run {
+            writeln!(
+                streams.stdout,
+                "Would stop and disable {}, then remove {}",
+                UNIT_NAME,
+                unit_path.display()
+            )
+            .expect("writeln! failed");
+            return Ok(0);
+        }
+        let result = systemctl(&["disable", "--now", UNIT_NAME])
+            .and_then(|_| {
+                fs::remove_file(&unit_path)
+                    .map_err(|e| format!("Couldn't remove {}: {}", unit_path.display(), e))
+            })
+            .and_then(|_| systemctl(&["daemon-reload"]));
+        Ok(report(
+            result,
+            &format!("Stopped and removed {}", unit_path.display()),
+            streams,
+        ))
+    }
+
+    pub fn systemd_unit(executable: &Path, daemon_args: &[String], real_user: &RealUser) -> String {
+        let mut command_line = vec![
+            executable.to_string_lossy().to_string(),
+            "--initialization".to_string(),
+        ];
+        command_line.extend(daemon_args.iter().cloned());
+        let uid = real_user.uid_opt.expect("Real user has no uid");
+        let gid = real_user.gid_opt.expect("Real user has no gid");
+        format!(
+            "# Written by MASQNode --install-service; MASQNode --uninstall-service removes it\n\
+             [Unit]\n\
+             Description=MASQ Daemon\n\
+             Documentation=https://github.com/MASQ-Project/Node\n\
+             Wants=network-online.target\n\
+             After=network-online.target\n\
+             \n\
+             [Service]\n\
+             Type=simple\n\
+             ExecStart={}\n\
+             Environment={} {} {}\n\
+             Restart=on-failure\n\
+             RestartSec=5\n\
+             {}\
+             \n\
+             [Install]\n\
+             WantedBy=multi-user.target\n",
+            command_line
+                .iter()
+                .map(|arg| systemd_quote(arg))
+                .collect::<Vec<String>>()
+                .join(" "),
+            systemd_quote(&format!("SUDO_UID={}", uid)),
+            systemd_quote(&format!("SUDO_GID={}", gid)),
+            systemd_quote(&format!("MASQ_REAL_USER={}", real_user)),
+            SERVICE_HARDENING
+        )
+    }
+
+    // systemd expands % and $ itself, and splits on whitespace unless it's quoted
+    pub fn systemd_quote(arg: &str) -> String {
+        let escaped = arg
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('%', "%%")
+            .replace('$', "$$");
+        if escaped.is_empty() || escaped.contains(char::is_whitespace) || escaped != arg {
+            format!("\"{}\"", escaped)
+        } else {
+            escaped
+        }
+    }
+
+    fn unit_path() -> PathBuf {
+        Path::new(UNIT_DIRECTORY).join(UNIT_NAME)
+    }
+
+    fn systemctl(args: &[&str]) -> Result<(), String> {
+        match Command::new("systemctl").args(args).output() {
+            Ok(ref output) if output.status.success() => Ok(()),
+            Ok(output) => Err(format!(
+                "systemctl {} failed: {}",
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )),
+            Err(e) => Err(format!("Couldn't run systemctl: {}", e)),
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use super::*;
+    use std::ffi::{OsStr, OsString};
+    use std::io;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use windows_service::service::{
+        ServiceAccess, ServiceControl, ServiceControlAccept, ServiceErrorControl, ServiceExitCode,
+        ServiceInfo, ServiceStartType, ServiceState, ServiceStatus, ServiceType,
+    };
+    use windows_service::service_control_handler::{
+        ServiceControlHandlerResult, ServiceStatusHandle,
+    };
+    use windows_service::service_manager::{ServiceManager, ServiceManagerAccess};
+    use windows_service::{define_windows_service, service_control_handler, service_dispatcher};
+
+    const SERVICE_NAME: &str = "MASQDaemon";
+    const SERVICE_DISPLAY_NAME: &str = "MASQ Daemon";
+
+    pub fn install(
+        request: ServiceRequest,
+        streams: &mut StdStreams,
+    ) -> Result<i32, ConfiguratorError> {
+        let executable = std::env::current_exe().expect("Can't find MASQNode executable");
+        let mut launch_arguments = vec![
+            OsString::from("--initialization"),
+            OsString::from(WINDOWS_SERVICE_FLAG),
+        ];
+        launch_arguments.extend(request.daemon_args.iter().map(OsString::from));
+        if request.dry_run {
+            writeln!(
+                streams.stdout,
+                "Would register {} to run {} {:?} at startup",
+                SERVICE_NAME,
+                executable.display(),
+                launch_arguments
+            )
+            .expect("writeln! failed");
+            return Ok(0);
+        }
+        let info = ServiceInfo {
+            name: OsString::from(SERVICE_NAME),
+            display_name: OsString::from(SERVICE_DISPLAY_NAME),
+            service_type: ServiceType::OWN_PROCESS,
+            start_type: ServiceStartType::AutoStart,
+            error_control: ServiceErrorControl::Normal,
+            executable_path: executable,
+            launch_arguments,
+            dependencies: vec![],
+            account_name: None,
+            account_password: None,
+        };
+        let result = ServiceManager::local_computer(
+            None::<&str>,
+            ServiceManagerAccess::CONNECT | ServiceManagerAccess::CREATE_SERVICE,
+        )
+        .and_then(|manager| manager.create_service(&info, ServiceAccess::START))
+        .and_then(|service| service.start(&[] as &[&OsStr]))
+        .map_err(|e| format!("Couldn't install {}: {}", SERVICE_NAME, e));
+        Ok(report(
+            result,
+            &format!("Installed and started {}", SERVICE_NAME),
+            streams,
+        ))
+    }
+
+    pub fn uninstall(
+        request: ServiceRequest,
+        streams: &mut StdStreams,
+    ) -> Result<i32, ConfiguratorError> {
+        if request.dry_run {
+            writeln!(streams.stdout, "Would stop and remove {}", SERVICE_NAME)
+                .expect("writeln! failed");
+            return Ok(0);
+        }
+        let result = ServiceManager::local_computer(None::<&str>, ServiceManagerAccess::CONNECT)
+            .and_then(|manager| {
+                manager.open_service(
+                    SERVICE_NAME,
+                    ServiceAccess::QUERY_STATUS | ServiceAccess::STOP | ServiceAccess::DELETE,
+                )
+            })
+            .and_then(|service| {
+                if service.query_status()?.current_state != ServiceState::Stopped {
+                    service.stop()?;
+                }
+                service.delete()
+            })
+            .map_err(|e| format!("Couldn't remove {}: {}", SERVICE_NAME, e));
+        Ok(report(
+            result,
+            &format!("Stopped and removed {}", SERVICE_NAME),
+            streams,
+        ))
+    }
+
+    define_windows_service!(ffi_service_main, service_main);
+
+    // Hands this process over to the Service Control Manager, which calls service_main
+    pub fn run_as_service() -> i32 {
+        match service_dispatcher::start(SERVICE_NAME, ffi_service_main) {
+            Ok(_) => 0,
+            Err(_) => 1,
+        }
+    }
+
+    fn service_main(_arguments: Vec<OsString>) {
+        let handle_arc: Arc<Mutex<Option<ServiceStatusHandle>>> = Arc::new(Mutex::new(None));
+        let inner_handle_arc = handle_arc.clone();
+        let register_result =
+            service_control_handler::register(SERVICE_NAME, move |control| match control {
+                ServiceControl::Stop | ServiceControl::Shutdown => {
+                    if let Some(handle) = *inner_handle_arc.lock().expect("Handle is poisoned") {
+                        let _ = handle.set_service_status(status(ServiceState::Stopped, 0));
+                    }
+                    std::process::exit(0)
+                }
+                ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+                _ => ServiceControlHandlerResult::NotImplemented,
+            });
+        let handle = match register_result {
+            Ok(handle) => handle,
+            Err(_) => return,
+        };
+        *handle_arc.lock().expect("Handle is poisoned") = Some(handle);
+        let _ = handle.set_service_status(status(ServiceState::Running, 0));
+        // Nobody's watching a service's console, so the Daemon gets none
+        let args = std::env::args()
+            .filter(|arg| arg != WINDOWS_SERVICE_FLAG)
+            .collect::<Vec<String>>();
+        let exit_code = crate::run_modes::RunModes::new().go(
+            &args,
+            &mut StdStreams {
+                stdin: &mut io::empty(),
+                stdout: &mut io::sink(),
+                stderr: &mut io::sink(),
+            },
+        );
+        let _ = handle.set_service_status(status(ServiceState::Stopped, exit_code as u32));
+    }
+
+    fn status(current_state: ServiceState, exit_code: u32) -> ServiceStatus {
+        ServiceStatus {
+            service_type: ServiceType::OWN_PROCESS,
+            current_state,
+            controls_accepted: match current_state {
+                ServiceState::Running => {
+                    ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN
+                }
+                _ => ServiceControlAccept::empty(),
+            },
+            exit_code: ServiceExitCode::Win32(exit_code),
+            checkpoint: 0,
+            wait_hint: Duration::default(),
+            process_id: None,
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+pub use platform::run_as_service as run_as_windows_service;
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+mod platform {
+    use super::*;
+
+    pub fn install(
+        _request: ServiceRequest,
+        _streams: &mut StdStreams,
+    ) -> Result<i32, ConfiguratorError> {
+        Err(unsupported(INSTALL_SERVICE_FLAG))
+    }
+
+    pub fn uninstall(
+        _request: ServiceRequest,
+        _streams: &mut StdStreams,
+    ) -> Result<i32, ConfiguratorError> {
+        Err(unsupported(UNINSTALL_SERVICE_FLAG))
+    }
+
+    fn unsupported(flag: &str) -> ConfiguratorError {
+        ConfiguratorError::required(
+            flag.trim_start_matches("--"),
+            "Only Linux with systemd and Windows are supported so far",
+        )
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "windows"))]
+fn report(result: Result<(), String>, success: &str, streams: &mut StdStreams) -> i32 {
+    match result {
+        Ok(()) => {
+            writeln!(streams.stdout, "{}", success).expect("writeln! failed");
+            0
+        }
+        Err(e) => {
+            writeln!(streams.stderr, "{}", e).expect("writeln! failed");
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use masq_lib::test_utils::fake_stream_holder::FakeStreamHolder;
+
+    fn args(strs: &[&str]) -> Vec<String> {
+        strs.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn service_request_separates_the_daemons_parameters_from_its_own() {
+        let result = ServiceRequest::from_args(&args(&[
+            "MASQNode",
+            "--install-service",
+            "--ui-port",
+            "5335",
+            "--real-user",
+            "1000:1000:/home/booga",
+            "--dry-run",
+            "--restart-max-retries",
+            "3",
+        ]));
+
+        assert_eq!(
+            result,
+            ServiceRequest {
+                daemon_args: args(&["--ui-port", "5335", "--restart-max-retries", "3"]),
+                real_user_opt: Some("1000:1000:/home/booga".to_string()),
+                dry_run: true,
+            }
+        );
+    }
+
+    #[test]
+    fn bad_daemon_parameters_are_caught_at_installation() {
+        let mut holder = FakeStreamHolder::new();
+
+        let result = install_service(
+            &args(&["MASQNode", "--install-service", "--ui-port", "booga"]),
+            &mut holder.streams(),
+        );
+
+        assert_eq!(result.is_err(), true);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn root_is_not_a_real_user() {
+        let request = ServiceRequest::from_args(&args(&[
+            "MASQNode",
+            "--install-service",
+            "--real-user",
+            "0:0:/root",
+        ]));
+
+        let result = request.real_user();
+
+        assert_eq!(
+            result.err().unwrap().param_errors[0].parameter,
+            "real-user".to_string()
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn systemd_unit_runs_the_daemon_for_the_real_user_with_few_privileges() {
+        let real_user = RealUser::new(Some(1000), Some(1001), Some("/home/booga".into()));
+
+        let result = platform::systemd_unit(
+            std::path::Path::new("/opt/masq/MASQNode"),
+            &args(&["--ui-port", "5335"]),
+            &real_user,
+        );
+
+        assert_eq!(
+            result.contains("\nExecStart=/opt/masq/MASQNode --initialization --ui-port 5335\n"),
+            true,
+            "{}",
+            result
+        );
+        assert_eq!(
+            result.contains(
+                "\nEnvironment=SUDO_UID=1000 SUDO_GID=1001 MASQ_REAL_USER=1000:1001:/home/booga\n"
+            ),
+            true,
+            "{}",
+            result
+        );
+        assert_eq!(
+            result.contains("\nCapabilityBoundingSet=CAP_NET_BIND_SERVICE CAP_SETUID "),
+            true,
+            "{}",
+            result
+        );
+        assert_eq!(result.contains("\nNoNewPrivileges=true\n"), true);
+        assert_eq!(result.ends_with("\nWantedBy=multi-user.target\n"), true);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn systemd_quoting_protects_spaces_and_specifiers() {
+        assert_eq!(platform::systemd_quote("plain"), "plain");
+        assert_eq!(
+            platform::systemd_quote("/home/my user/MASQNode"),
+            "\"/home/my user/MASQNode\""
+        );
+        assert_eq!(platform::systemd_quote("100%$"), "\"100%%$$\"");
+        assert_eq!(platform::systemd_quote("say \"hi\""), "\"say \\\"hi\\\"\"");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn dry_run_prints_the_unit_instead_of_installing_it() {
+        let mut holder = FakeStreamHolder::new();
+
+        let result = install_service(
+            &args(&[
+                "MASQNode",
+                "--install-service",
+                "--dry-run",
+                "--real-user",
+                "1000:1000:/home/booga",
+            ]),
+            &mut holder.streams(),
+        );
+
+        assert_eq!(result, Ok(0));
+        let expected = platform::systemd_unit(
+            &std::env::current_exe().unwrap(),
+            &[],
+            &RealUser::new(Some(1000), Some(1000), Some("/home/booga".into())),
+        );
+        assert_eq!(holder.stdout.get_string(), expected);
+    }
+}
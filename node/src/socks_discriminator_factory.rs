@@ -0,0 +1,55 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use crate::discriminator::Discriminator;
+use crate::discriminator::DiscriminatorFactory;
+use crate::null_masquerader::NullMasquerader;
+use crate::sub_lib::raw_framer::RawFramer;
+
+#[derive(Debug, Default)]
+pub struct SocksDiscriminatorFactory {}
+
+impl DiscriminatorFactory for SocksDiscriminatorFactory {
+    fn make(&self) -> Discriminator {
+        Discriminator::new(
+            Box::new(RawFramer::new()),
+            vec![Box::new(NullMasquerader::new())],
+        )
+    }
+
+    fn duplicate(&self) -> Box<dyn DiscriminatorFactory> {
+        Box::new(SocksDiscriminatorFactory {})
+    }
+}
+
+impl SocksDiscriminatorFactory {
+    pub fn new() -> SocksDiscriminatorFactory {
+        Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::discriminator::UnmaskedChunk;
+
+    #[test]
+    fn discriminator_factory_duplicate_works() {
+        let subject = SocksDiscriminatorFactory::new();
+
+        subject.duplicate();
+
+        // no panic; test passes
+    }
+
+    #[test]
+    fn factory_makes_discriminator_that_passes_socks_data_through_to_proxy_server() {
+        let data: &[u8] = &[0x05, 0x01, 0x00];
+        let subject = SocksDiscriminatorFactory::new();
+
+        let mut result = subject.make();
+
+        result.add_data(data);
+        let chunk = result.take_chunk();
+        assert_eq!(chunk, Some(UnmaskedChunk::new(data.to_vec(), true, true)));
+        assert_eq!(result.take_chunk(), None);
+    }
+}
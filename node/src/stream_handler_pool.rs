@@ -1,12 +1,15 @@
 // Copyright (c) 2017-2019, Substratum LLC (https://substratum.net) and/or its affiliates. All rights reserved.
 use crate::bootstrapper::PortConfiguration;
 use crate::discriminator::DiscriminatorFactory;
-use crate::json_masquerader::JsonMasquerader;
+use crate::masquerade_profile;
 use crate::masquerader::Masquerader;
 use crate::stream_messages::*;
 use crate::stream_reader::StreamReaderReal;
 use crate::stream_writer_sorted::StreamWriterSorted;
 use crate::stream_writer_unsorted::StreamWriterUnsorted;
+use crate::sub_lib::channel_windows::{
+    ChannelOffer, ChannelWindows, CHANNEL_HOLD_BYTES, CHANNEL_WINDOW_BYTES,
+};
 use crate::sub_lib::channel_wrappers::FuturesChannelFactory;
 use crate::sub_lib::channel_wrappers::FuturesChannelFactoryReal;
 use crate::sub_lib::channel_wrappers::SenderWrapper;
@@ -14,17 +17,23 @@ use crate::sub_lib::cryptde::PublicKey;
 use crate::sub_lib::dispatcher;
 use crate::sub_lib::dispatcher::Endpoint;
 use crate::sub_lib::dispatcher::{DispatcherSubs, StreamShutdownMsg};
+use crate::sub_lib::hole_punch;
 use crate::sub_lib::logger::Logger;
+use crate::sub_lib::metrics;
 use crate::sub_lib::neighborhood::DispatcherNodeQueryMessage;
 use crate::sub_lib::neighborhood::NodeQueryMessage;
 use crate::sub_lib::neighborhood::NodeQueryResponseMetadata;
 use crate::sub_lib::neighborhood::RemoveNeighborMessage;
 use crate::sub_lib::neighborhood::ZERO_RATE_PACK;
+use crate::sub_lib::neighborhood::{
+    ConnectionAuditMessage, ConnectionAuditRecord, ConnectionDirection,
+};
 use crate::sub_lib::node_addr::NodeAddr;
 use crate::sub_lib::sequence_buffer::SequencedPacket;
 use crate::sub_lib::stream_connector::StreamConnector;
 use crate::sub_lib::stream_connector::StreamConnectorReal;
 use crate::sub_lib::stream_handler_pool::DispatcherNodeQueryResponse;
+use crate::sub_lib::stream_handler_pool::PunchHoleMsg;
 use crate::sub_lib::stream_handler_pool::TransmitDataMsg;
 use crate::sub_lib::tokio_wrappers::ReadHalfWrapper;
 use crate::sub_lib::tokio_wrappers::WriteHalfWrapper;
@@ -38,8 +47,9 @@ use masq_lib::utils::localhost;
 use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
 use std::net::SocketAddr;
+use std::sync::Arc;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tokio::prelude::Future;
 
 // IMPORTANT: Nothing at or below the level of StreamHandlerPool should know about StreamKeys.
@@ -55,6 +65,7 @@ pub struct StreamHandlerPoolSubs {
     pub remove_sub: Recipient<RemoveStreamMsg>,
     pub bind: Recipient<PoolBindMessage>,
     pub node_query_response: Recipient<DispatcherNodeQueryResponse>,
+    pub punch_hole_sub: Recipient<PunchHoleMsg>,
 }
 
 impl Clone for StreamHandlerPoolSubs {
@@ -65,10 +76,13 @@ impl Clone for StreamHandlerPoolSubs {
             remove_sub: self.remove_sub.clone(),
             bind: self.bind.clone(),
             node_query_response: self.node_query_response.clone(),
+            punch_hole_sub: self.punch_hole_sub.clone(),
         }
     }
 }
 
+// Everything for a neighbor goes over one connection, whichever of its ports it came in on and
+// however many routes go through it: only local connections are told apart by port.
 #[derive(Hash, PartialEq, Eq, Copy, Clone, Debug)]
 struct StreamWriterKey {
     socket_addr: SocketAddr,
@@ -96,12 +110,59 @@ impl Display for StreamWriterKey {
     }
 }
 
+// What the connection audit log will want to know about a clandestine connection once it closes
+struct OpenConnection {
+    public_key_opt: Option<PublicKey>,
+    peer_addr: SocketAddr,
+    direction: ConnectionDirection,
+    opened_at: SystemTime,
+    opened_instant: Instant,
+    bytes_out: u64,
+}
+
+impl OpenConnection {
+    fn new(
+        public_key_opt: Option<PublicKey>,
+        peer_addr: SocketAddr,
+        direction: ConnectionDirection,
+    ) -> OpenConnection {
+        OpenConnection {
+            public_key_opt,
+            peer_addr,
+            direction,
+            opened_at: SystemTime::now(),
+            opened_instant: Instant::now(),
+            bytes_out: 0,
+        }
+    }
+
+    fn into_audit_record(self, bytes_in: u64, reason: &DisconnectReason) -> ConnectionAuditRecord {
+        ConnectionAuditRecord {
+            public_key_opt: self.public_key_opt,
+            peer_addr: self.peer_addr,
+            direction: self.direction,
+            opened_at: self
+                .opened_at
+                .duration_since(UNIX_EPOCH)
+                .map(|since| since.as_secs())
+                .unwrap_or(0),
+            duration_ms: self.opened_instant.elapsed().as_millis() as u64,
+            bytes_in,
+            bytes_out: self.bytes_out,
+            disconnect_reason: reason.to_string(),
+        }
+    }
+}
+
 pub struct StreamHandlerPool {
     stream_writers: HashMap<StreamWriterKey, Option<Box<dyn SenderWrapper<SequencedPacket>>>>,
+    open_connections: HashMap<StreamWriterKey, OpenConnection>,
+    channel_windows: HashMap<StreamWriterKey, Arc<ChannelWindows>>,
     dispatcher_subs: Option<DispatcherSubs>,
     self_subs: Option<StreamHandlerPoolSubs>,
     ask_neighborhood: Option<Recipient<DispatcherNodeQueryMessage>>,
     tell_neighborhood: Option<Recipient<RemoveNeighborMessage>>,
+    audit_connection: Option<Recipient<ConnectionAuditMessage>>,
     logger: Logger,
     stream_connector: Box<dyn StreamConnector>,
     channel_factory: Box<dyn FuturesChannelFactory<SequencedPacket>>,
@@ -144,6 +205,14 @@ impl Handler<DispatcherNodeQueryResponse> for StreamHandlerPool {
     }
 }
 
+impl Handler<PunchHoleMsg> for StreamHandlerPool {
+    type Result = ();
+
+    fn handle(&mut self, msg: PunchHoleMsg, _ctx: &mut Self::Context) {
+        self.handle_punch_hole_msg(msg)
+    }
+}
+
 impl Handler<PoolBindMessage> for StreamHandlerPool {
     type Result = ();
 
@@ -153,6 +222,7 @@ impl Handler<PoolBindMessage> for StreamHandlerPool {
         self.self_subs = Some(msg.stream_handler_pool_subs);
         self.ask_neighborhood = Some(msg.neighborhood_subs.dispatcher_node_query);
         self.tell_neighborhood = Some(msg.neighborhood_subs.remove_neighbor);
+        self.audit_connection = Some(msg.neighborhood_subs.connection_audit);
     }
 }
 
@@ -162,10 +232,13 @@ impl StreamHandlerPool {
     ) -> StreamHandlerPool {
         StreamHandlerPool {
             stream_writers: HashMap::new(),
+            open_connections: HashMap::new(),
+            channel_windows: HashMap::new(),
             dispatcher_subs: None,
             self_subs: None,
             ask_neighborhood: None,
             tell_neighborhood: None,
+            audit_connection: None,
             logger: Logger::new("Dispatcher"),
             stream_connector: Box::new(StreamConnectorReal {}),
             channel_factory: Box::new(FuturesChannelFactoryReal {}),
@@ -181,6 +254,7 @@ impl StreamHandlerPool {
             remove_sub: recipient!(pool_addr, RemoveStreamMsg),
             bind: recipient!(pool_addr, PoolBindMessage),
             node_query_response: recipient!(pool_addr, DispatcherNodeQueryResponse),
+            punch_hole_sub: recipient!(pool_addr, PunchHoleMsg),
         }
     }
 
@@ -247,7 +321,16 @@ impl StreamHandlerPool {
             .insert(StreamWriterKey::from(peer_addr), Some(tx));
 
         if is_clandestine {
-            tokio::spawn(StreamWriterUnsorted::new(write_stream, peer_addr, rx));
+            let channel_windows = Arc::new(ChannelWindows::new(
+                CHANNEL_WINDOW_BYTES,
+                CHANNEL_HOLD_BYTES,
+            ));
+            self.channel_windows
+                .insert(StreamWriterKey::from(peer_addr), channel_windows.clone());
+            tokio::spawn(
+                StreamWriterUnsorted::new(write_stream, peer_addr, rx)
+                    .with_channel_windows(channel_windows),
+            );
         } else {
             tokio::spawn(StreamWriterSorted::new(write_stream, peer_addr, rx));
         };
@@ -308,6 +391,9 @@ impl StreamHandlerPool {
         msg: AddStreamMsg,
     ) -> <Self as Handler<AddStreamMsg>>::Result {
         let port_config = msg.port_configuration.clone();
+        if port_config.is_clandestine {
+            self.audit_opened_connection(msg.connection_info.peer_addr);
+        }
         self.set_up_stream_writer(
             msg.connection_info.writer,
             msg.connection_info.peer_addr,
@@ -322,19 +408,108 @@ impl StreamHandlerPool {
         );
     }
 
+    fn handle_punch_hole_msg(&mut self, msg: PunchHoleMsg) {
+        let sw_key = StreamWriterKey::from(msg.peer_addr);
+        if self.stream_writers.contains_key(&sw_key) {
+            debug!(
+                self.logger,
+                "Already have a stream keyed by {}: no need to punch a hole to {}",
+                sw_key,
+                msg.peer_addr
+            );
+            return;
+        }
+        let add_stream_sub = self
+            .self_subs
+            .as_ref()
+            .expect("StreamHandlerPool is unbound")
+            .add_sub
+            .clone();
+        let clandestine_discriminator_factories = self.clandestine_discriminator_factories.clone();
+        let logger_m = self.logger.clone();
+        let logger_me = self.logger.clone();
+        let peer_addr = msg.peer_addr;
+        let local_port = msg.local_port;
+        info!(
+            self.logger,
+            "Punching a hole from port {} to {}", local_port, peer_addr
+        );
+        let punch_future = hole_punch::punch(local_port, peer_addr, &self.logger)
+            .map(move |connection_info| {
+                info!(
+                    logger_m,
+                    "Punched a hole from port {} to {}", local_port, peer_addr
+                );
+                add_stream_sub
+                    .try_send(AddStreamMsg {
+                        connection_info,
+                        origin_port: Some(local_port),
+                        port_configuration: PortConfiguration::new(
+                            clandestine_discriminator_factories,
+                            true,
+                        ),
+                    })
+                    .expect("StreamHandlerPool is dead");
+            })
+            .map_err(move |e| {
+                warning!(
+                    logger_me,
+                    "Could not punch a hole from port {} to {}: {}",
+                    local_port,
+                    peer_addr,
+                    e
+                )
+            });
+        tokio::spawn(punch_future);
+    }
+
     fn handle_remove_stream_msg(&mut self, msg: RemoveStreamMsg) {
         let stream_writer_key = StreamWriterKey::from(msg.peer_addr);
         debug!(
             self.logger,
-            "Stream from local {} to peer {} has closed; removing writer with key {}",
-            msg.local_addr,
-            msg.peer_addr,
-            stream_writer_key
+            "Stream from local {} to peer {} has closed", msg.local_addr, msg.peer_addr
         );
-        let report_to_counterpart = match self.stream_writers.remove(&stream_writer_key) {
-            None | Some(None) => false,
-            Some(Some(_sender_wrapper)) => true,
+        let half_closed = match &msg.stream_type {
+            RemovedStreamType::NonClandestine(nca) => nca.half_closed,
+            RemovedStreamType::Clandestine => false,
+        };
+        let superseded = msg.stream_type == RemovedStreamType::Clandestine
+            && self
+                .open_connections
+                .get(&stream_writer_key)
+                .map(|connection| connection.peer_addr != msg.peer_addr)
+                .unwrap_or(false);
+        let report_to_counterpart = if superseded {
+            // A newer connection to the same neighbor carries its traffic now; leave it alone
+            debug!(
+                self.logger,
+                "Redundant connection from {} closed; traffic keyed by {} still shares the one from {}",
+                msg.peer_addr,
+                stream_writer_key,
+                self.open_connections[&stream_writer_key].peer_addr
+            );
+            false
+        } else if half_closed {
+            // Keep the writer, so that the rest of the response can still get to the client
+            match self.stream_writers.get(&stream_writer_key) {
+                None | Some(None) => false,
+                Some(Some(_sender_wrapper)) => true,
+            }
+        } else {
+            if msg.stream_type == RemovedStreamType::Clandestine {
+                self.channel_windows.remove(&stream_writer_key);
+            }
+            match self.stream_writers.remove(&stream_writer_key) {
+                None => false,
+                Some(writer_opt) => {
+                    debug!(self.logger, "Removed writer with key {}", stream_writer_key);
+                    writer_opt.is_some()
+                }
+            }
         };
+        if msg.stream_type == RemovedStreamType::Clandestine && !superseded {
+            self.audit_closed_connection(&stream_writer_key, msg.bytes_read, &msg.reason);
+        }
         let stream_shutdown_msg = StreamShutdownMsg {
             peer_addr: msg.peer_addr,
             stream_type: msg.stream_type,
@@ -346,6 +521,43 @@ impl StreamHandlerPool {
             .expect("StreamShutdownMsg target is dead");
     }
 
+    // A connection we opened is already being audited, with the key we opened it to
+    fn audit_opened_connection(&mut self, peer_addr: SocketAddr) {
+        let sw_key = StreamWriterKey::from(peer_addr);
+        match self.open_connections.get_mut(&sw_key) {
+            Some(connection) => {
+                connection.peer_addr = peer_addr;
+                connection.opened_at = SystemTime::now();
+                connection.opened_instant = Instant::now();
+            }
+            None => {
+                self.open_connections.insert(
+                    sw_key,
+                    OpenConnection::new(None, peer_addr, ConnectionDirection::Inbound),
+                );
+            }
+        }
+    }
+
+    fn audit_closed_connection(
+        &mut self,
+        sw_key: &StreamWriterKey,
+        bytes_in: u64,
+        reason: &DisconnectReason,
+    ) {
+        let connection = match self.open_connections.remove(sw_key) {
+            Some(connection) => connection,
+            None => return,
+        };
+        if let Some(audit_connection) = self.audit_connection.as_ref() {
+            audit_connection
+                .try_send(ConnectionAuditMessage {
+                    record: connection.into_audit_record(bytes_in, reason),
+                })
+                .expect("Neighborhood is dead");
+        }
+    }
+
     // TODO: This method is wayyyy too big
     fn handle_dispatcher_node_query_response(&mut self, msg: DispatcherNodeQueryResponse) {
         // TODO Can be recombined with TransmitDataMsg after SC-358/GH-96
@@ -393,7 +605,8 @@ impl StreamHandlerPool {
                 );
                 debug!(self.logger, "Masking {} bytes", msg.context.data.len());
                 let packet = if msg.context.sequence_number.is_none() {
-                    let masquerader = self.traffic_analyzer.get_masquerader();
+                    let public_key = &msg.result.as_ref().expect("Key disappeared").public_key;
+                    let masquerader = self.traffic_analyzer.get_masquerader(public_key);
                     match masquerader.mask(msg.context.data.as_slice()) {
                         Ok(masked_data) => SequencedPacket::new(masked_data, 0, false),
                         Err(e) => {
@@ -412,6 +625,40 @@ impl StreamHandlerPool {
                 };
 
                 let packet_len = packet.data.len();
+                let offer = match self.channel_windows.get(&sw_key) {
+                    Some(channel_windows) if msg.context.sequence_number.is_none() => {
+                        channel_windows.offer(msg.context.relayed_from_opt, packet)
+                    }
+                    _ => ChannelOffer::Send(packet),
+                };
+                let packet = match offer {
+                    ChannelOffer::Send(packet) => packet,
+                    ChannelOffer::Dropped => {
+                        warning!(
+                            self.logger,
+                            "Dropping {} bytes relayed from {:?}: that channel to {} is holding all it can",
+                            packet_len,
+                            msg.context.relayed_from_opt,
+                            peer_addr
+                        );
+                        return;
+                    }
+                    ChannelOffer::Held => {
+                        // The writer sends it once its channel has room
+                        metrics::HELD_PACKAGES.add(1);
+                        debug!(
+                            self.logger,
+                            "Holding {} bytes relayed from {:?} until that channel to {} has room",
+                            packet_len,
+                            msg.context.relayed_from_opt,
+                            peer_addr
+                        );
+                        if let Some(connection) = self.open_connections.get_mut(&sw_key) {
+                            connection.bytes_out += packet_len as u64;
+                        }
+                        return;
+                    }
+                };
                 match tx_box.unbounded_send(packet) {
                     Err(e) => {
                         debug!(
@@ -423,9 +670,18 @@ impl StreamHandlerPool {
                         );
                         self.stream_writers
                             .remove(&StreamWriterKey::from(peer_addr));
+                        self.channel_windows.remove(&sw_key);
                     }
                     Ok(_) => {
                         debug!(self.logger, "Queued {} bytes for transmission", packet_len);
+                        if let Some(connection) = self.open_connections.get_mut(&sw_key) {
+                            connection.bytes_out += packet_len as u64;
+                            let public_key =
+                                &msg.result.as_ref().expect("Key disappeared").public_key;
+                            if connection.public_key_opt.is_none() && !public_key.is_empty() {
+                                connection.public_key_opt = Some(public_key.clone());
+                            }
+                        }
                     }
                 };
                 if msg.context.last_data {
@@ -493,6 +749,14 @@ impl StreamHandlerPool {
                     .clone()
                     .map(|d| d.public_key)
                     .expect("Key magically disappeared");
+                self.open_connections.insert(
+                    StreamWriterKey::from(peer_addr),
+                    OpenConnection::new(
+                        Some(key.clone()),
+                        peer_addr,
+                        ConnectionDirection::Outbound,
+                    ),
+                );
                 let sub = self
                     .dispatcher_subs
                     .as_ref()
@@ -517,6 +781,8 @@ impl StreamHandlerPool {
                             peer_addr: peer_addr_e,
                             local_addr: SocketAddr::new (localhost(), 0), // irrelevant; stream was never opened
                             stream_type: RemovedStreamType::Clandestine,
+                            bytes_read: 0,
+                            reason: DisconnectReason::ConnectFailed(err.to_string()),
                             sub,
                         }).expect("StreamHandlerPool is dead");
 
@@ -532,14 +798,14 @@ impl StreamHandlerPool {
 }
 
 trait TrafficAnalyzer {
-    fn get_masquerader(&self) -> Box<dyn Masquerader>;
+    fn get_masquerader(&self, public_key: &PublicKey) -> Box<dyn Masquerader>;
 }
 
 struct TrafficAnalyzerReal {}
 
 impl TrafficAnalyzer for TrafficAnalyzerReal {
-    fn get_masquerader(&self) -> Box<dyn Masquerader> {
-        Box::new(JsonMasquerader::new())
+    fn get_masquerader(&self, public_key: &PublicKey) -> Box<dyn Masquerader> {
+        masquerade_profile::profile_for(public_key).masquerader()
     }
 }
 
@@ -569,6 +835,7 @@ mod tests {
     use crate::test_utils::tokio_wrapper_mocks::ReadHalfWrapperMock;
     use crate::test_utils::tokio_wrapper_mocks::WriteHalfWrapperMock;
     use crate::test_utils::{await_messages, main_cryptde};
+    use crate::websocket_masquerader::WebSocketMasquerader;
     use actix::Actor;
     use actix::Addr;
     use actix::System;
@@ -588,11 +855,36 @@ mod tests {
     struct TrafficAnalyzerMock {}
 
     impl TrafficAnalyzer for TrafficAnalyzerMock {
-        fn get_masquerader(&self) -> Box<dyn Masquerader> {
+        fn get_masquerader(&self, _public_key: &PublicKey) -> Box<dyn Masquerader> {
             Box::new(FailingMasquerader {})
         }
     }
 
+    #[test]
+    fn traffic_analyzer_masks_in_the_profile_each_peer_asked_for() {
+        let asking_key = PublicKey::new(b"traffic analyzer asking");
+        let silent_key = PublicKey::new(b"traffic analyzer silent");
+        crate::sub_lib::cipher_suite::record_peer_features(
+            &asking_key,
+            &vec![masquerade_profile::WEBSOCKET_PROFILE.to_string()]
+                .into_iter()
+                .collect(),
+        );
+        let subject = TrafficAnalyzerReal {};
+
+        let asking_result = subject.get_masquerader(&asking_key).mask(b"booga").unwrap();
+        let silent_result = subject.get_masquerader(&silent_key).mask(b"booga").unwrap();
+
+        assert_eq!(
+            asking_result,
+            WebSocketMasquerader::new().mask(b"booga").unwrap()
+        );
+        assert_eq!(
+            silent_result,
+            JsonMasquerader::new().mask(b"booga").unwrap()
+        );
+    }
+
     #[test]
     fn a_newly_added_stream_produces_stream_handler_that_sends_received_data_to_dispatcher() {
         let dispatcher = Recorder::new();
@@ -703,7 +995,8 @@ mod tests {
                 peer_addr: peer_addr_a,
                 stream_type: RemovedStreamType::NonClandestine(NonClandestineAttributes {
                     reception_port: reception_port.unwrap(),
-                    sequence_number: 3
+                    sequence_number: 3,
+                    half_closed: false,
                 }),
                 report_to_counterpart: true,
             }
@@ -766,6 +1059,7 @@ mod tests {
                     last_data: true,
                     sequence_number: Some(0),
                     data: b"hello".to_vec(),
+                    relayed_from_opt: None,
                 })
                 .unwrap();
 
@@ -848,6 +1142,7 @@ mod tests {
                 last_data: true,
                 sequence_number: Some(0),
                 data: vec![0x12, 0x34],
+                relayed_from_opt: None,
             })
             .unwrap();
 
@@ -871,6 +1166,7 @@ mod tests {
                 last_data: true,
                 sequence_number: Some(0),
                 data: vec![0x56, 0x78],
+                relayed_from_opt: None,
             })
             .unwrap();
 
@@ -938,6 +1234,8 @@ mod tests {
                     peer_addr,
                     local_addr,
                     stream_type: RemovedStreamType::Clandestine,
+                    bytes_read: 0,
+                    reason: DisconnectReason::PeerClosed,
                     sub: peer_actors.dispatcher.stream_shutdown_sub,
                 })
                 .unwrap();
@@ -949,6 +1247,7 @@ mod tests {
                     last_data: true,
                     sequence_number: Some(0),
                     data: vec![0x12, 0x34],
+                    relayed_from_opt: None,
                 })
                 .unwrap();
 
@@ -979,6 +1278,8 @@ mod tests {
             peer_addr,
             local_addr,
             stream_type: RemovedStreamType::Clandestine,
+            bytes_read: 0,
+            reason: DisconnectReason::PeerClosed,
             sub,
         });
 
@@ -1013,7 +1314,10 @@ mod tests {
             stream_type: RemovedStreamType::NonClandestine(NonClandestineAttributes {
                 reception_port: HTTP_PORT,
                 sequence_number: 1234,
+                half_closed: false,
             }),
+            bytes_read: 0,
+            reason: DisconnectReason::PeerClosed,
             sub,
         });
 
@@ -1028,13 +1332,56 @@ mod tests {
                 peer_addr,
                 stream_type: RemovedStreamType::NonClandestine(NonClandestineAttributes {
                     reception_port: HTTP_PORT,
-                    sequence_number: 1234
+                    sequence_number: 1234,
+                    half_closed: false,
                 }),
                 report_to_counterpart: false
             }
         );
     }
 
+    #[test]
+    fn handle_remove_stream_msg_keeps_writer_for_half_closed_stream() {
+        let (recorder, _, recording_arc) = make_recorder();
+        let system = System::new("test");
+        let sub = recorder.start().recipient::<StreamShutdownMsg>();
+        let mut subject = StreamHandlerPool::new(vec![]);
+        let peer_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
+        let local_addr = SocketAddr::from_str("127.0.0.1:0").unwrap();
+        let sw_key = StreamWriterKey::from(peer_addr);
+        subject
+            .stream_writers
+            .insert(sw_key, Some(Box::new(SenderWrapperMock::new(local_addr))));
+        let stream_type = RemovedStreamType::NonClandestine(NonClandestineAttributes {
+            reception_port: HTTP_PORT,
+            sequence_number: 1234,
+            half_closed: true,
+        });
+
+        subject.handle_remove_stream_msg(RemoveStreamMsg {
+            peer_addr,
+            local_addr,
+            stream_type: stream_type.clone(),
+            bytes_read: 0,
+            reason: DisconnectReason::PeerClosed,
+            sub,
+        });
+
+        System::current().stop_with_code(0);
+        system.run();
+        assert_eq!(subject.stream_writers.contains_key(&sw_key), true);
+        let recording = recording_arc.lock().unwrap();
+        let record = recording.get_record::<StreamShutdownMsg>(0);
+        assert_eq!(
+            record,
+            &StreamShutdownMsg {
+                peer_addr,
+                stream_type,
+                report_to_counterpart: true
+            }
+        );
+    }
+
     #[test]
     fn handle_remove_stream_msg_handles_stream_waiting_for_connect_scenario() {
         let (recorder, _, recording_arc) = make_recorder();
@@ -1050,6 +1397,8 @@ mod tests {
             peer_addr,
             local_addr,
             stream_type: RemovedStreamType::Clandestine,
+            bytes_read: 0,
+            reason: DisconnectReason::PeerClosed,
             sub,
         });
 
@@ -1068,6 +1417,160 @@ mod tests {
         );
     }
 
+    #[test]
+    fn handle_remove_stream_msg_audits_a_closed_clandestine_connection() {
+        let (neighborhood, _, neighborhood_recording_arc) = make_recorder();
+        let (dispatcher, _, _) = make_recorder();
+        let system = System::new("test");
+        let sub = dispatcher.start().recipient::<StreamShutdownMsg>();
+        let mut subject = StreamHandlerPool::new(vec![]);
+        subject.audit_connection = Some(neighborhood.start().recipient::<ConnectionAuditMessage>());
+        let peer_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
+        let local_addr = SocketAddr::from_str("2.3.4.5:6789").unwrap();
+        let before = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        subject.audit_opened_connection(peer_addr);
+        subject
+            .open_connections
+            .get_mut(&StreamWriterKey::from(peer_addr))
+            .unwrap()
+            .bytes_out = 4321;
+
+        subject.handle_remove_stream_msg(RemoveStreamMsg {
+            peer_addr,
+            local_addr,
+            stream_type: RemovedStreamType::Clandestine,
+            bytes_read: 1234,
+            reason: DisconnectReason::StreamError("connection reset".to_string()),
+            sub,
+        });
+
+        System::current().stop_with_code(0);
+        system.run();
+        let after = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        assert!(subject.open_connections.is_empty());
+        let recording = neighborhood_recording_arc.lock().unwrap();
+        let record = &recording.get_record::<ConnectionAuditMessage>(0).record;
+        assert!(before <= record.opened_at && record.opened_at <= after);
+        assert_eq!(
+            record,
+            &ConnectionAuditRecord {
+                public_key_opt: None,
+                peer_addr,
+                direction: ConnectionDirection::Inbound,
+                opened_at: record.opened_at,
+                duration_ms: record.duration_ms,
+                bytes_in: 1234,
+                bytes_out: 4321,
+                disconnect_reason: "stream error: connection reset".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn closing_a_redundant_connection_leaves_the_one_a_neighbors_traffic_shares() {
+        init_test_logging();
+        let (neighborhood, _, neighborhood_recording_arc) = make_recorder();
+        let (dispatcher, _, dispatcher_recording_arc) = make_recorder();
+        let system = System::new("test");
+        let sub = dispatcher.start().recipient::<StreamShutdownMsg>();
+        let mut subject = StreamHandlerPool::new(vec![]);
+        subject.audit_connection = Some(neighborhood.start().recipient::<ConnectionAuditMessage>());
+        let old_peer_addr = SocketAddr::from_str("6.7.8.9:5678").unwrap();
+        let new_peer_addr = SocketAddr::from_str("6.7.8.9:6789").unwrap();
+        let local_addr = SocketAddr::from_str("2.3.4.5:7890").unwrap();
+        let sw_key = StreamWriterKey::from(new_peer_addr);
+        subject.audit_opened_connection(old_peer_addr);
+        subject.audit_opened_connection(new_peer_addr);
+        subject.stream_writers.insert(
+            sw_key,
+            Some(Box::new(SenderWrapperMock::new(new_peer_addr))),
+        );
+
+        subject.handle_remove_stream_msg(RemoveStreamMsg {
+            peer_addr: old_peer_addr,
+            local_addr,
+            stream_type: RemovedStreamType::Clandestine,
+            bytes_read: 1234,
+            reason: DisconnectReason::PeerClosed,
+            sub,
+        });
+
+        System::current().stop_with_code(0);
+        system.run();
+        assert_eq!(subject.stream_writers.contains_key(&sw_key), true);
+        assert_eq!(subject.open_connections[&sw_key].peer_addr, new_peer_addr);
+        assert_eq!(neighborhood_recording_arc.lock().unwrap().len(), 0);
+        let dispatcher_recording = dispatcher_recording_arc.lock().unwrap();
+        assert_eq!(
+            dispatcher_recording.get_record::<StreamShutdownMsg>(0),
+            &StreamShutdownMsg {
+                peer_addr: old_peer_addr,
+                stream_type: RemovedStreamType::Clandestine,
+                report_to_counterpart: false
+            }
+        );
+        let tlh = TestLogHandler::new();
+        tlh.exists_log_containing("Stream from local 2.3.4.5:7890 to peer 6.7.8.9:5678 has closed");
+        tlh.exists_no_log_containing("Removed writer with key 6.7.8.9:*");
+    }
+
+    #[test]
+    fn connection_opened_by_this_node_keeps_its_key_and_direction_when_the_stream_is_added() {
+        let mut subject = StreamHandlerPool::new(vec![]);
+        let public_key = PublicKey::new(&[1, 2, 3, 4]);
+        let peer_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
+        let sw_key = StreamWriterKey::from(peer_addr);
+        subject.open_connections.insert(
+            sw_key,
+            OpenConnection::new(
+                Some(public_key.clone()),
+                peer_addr,
+                ConnectionDirection::Outbound,
+            ),
+        );
+
+        subject.audit_opened_connection(peer_addr);
+
+        let connection = subject.open_connections.get(&sw_key).unwrap();
+        assert_eq!(connection.public_key_opt, Some(public_key));
+        assert_eq!(connection.direction, ConnectionDirection::Outbound);
+    }
+
+    #[test]
+    fn non_clandestine_streams_are_not_audited() {
+        let (neighborhood, _, neighborhood_recording_arc) = make_recorder();
+        let (dispatcher, _, _) = make_recorder();
+        let system = System::new("test");
+        let sub = dispatcher.start().recipient::<StreamShutdownMsg>();
+        let mut subject = StreamHandlerPool::new(vec![]);
+        subject.audit_connection = Some(neighborhood.start().recipient::<ConnectionAuditMessage>());
+        let peer_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
+        subject.audit_opened_connection(peer_addr);
+
+        subject.handle_remove_stream_msg(RemoveStreamMsg {
+            peer_addr,
+            local_addr: SocketAddr::from_str("127.0.0.1:80").unwrap(),
+            stream_type: RemovedStreamType::NonClandestine(NonClandestineAttributes {
+                reception_port: HTTP_PORT,
+                sequence_number: 0,
+                half_closed: false,
+            }),
+            bytes_read: 1234,
+            reason: DisconnectReason::PeerClosed,
+            sub,
+        });
+
+        System::current().stop_with_code(0);
+        system.run();
+        assert_eq!(neighborhood_recording_arc.lock().unwrap().len(), 0);
+    }
+
     #[test]
     fn when_stream_handler_pool_fails_to_create_nonexistent_stream_for_write_then_it_logs_and_notifies_neighborhood(
     ) {
@@ -1113,6 +1616,7 @@ mod tests {
                         last_data: false,
                         sequence_number: None,
                         data: b"hello".to_vec(),
+                        relayed_from_opt: None,
                     },
                 })
                 .unwrap();
@@ -1121,10 +1625,22 @@ mod tests {
         });
 
         TestLogHandler::new().await_log_containing("ERROR: Dispatcher: Stream to 1.2.3.5:7000 does not exist and could not be connected; discarding 5 bytes: other os error", 1000);
-        neighborhood_awaiter.await_message_count(1);
+        neighborhood_awaiter.await_message_count(2);
         let remove_neighbor_msg =
             Recording::get::<RemoveNeighborMessage>(&neighborhood_recording_arc, 0);
         assert_eq!(remove_neighbor_msg.public_key, expected_key);
+        let audit_record =
+            Recording::get::<ConnectionAuditMessage>(&neighborhood_recording_arc, 1).record;
+        assert_eq!(audit_record.public_key_opt, Some(expected_key));
+        assert_eq!(
+            audit_record.peer_addr,
+            SocketAddr::from_str("1.2.3.5:7000").unwrap()
+        );
+        assert_eq!(audit_record.direction, ConnectionDirection::Outbound);
+        assert_eq!(
+            audit_record.disconnect_reason,
+            "connection failed: other os error".to_string()
+        );
 
         let connect_pair_params = connect_pair_params_arc_a.lock().unwrap();
         let connect_pair_params_vec: &Vec<SocketAddr> = connect_pair_params.as_ref();
@@ -1200,6 +1716,7 @@ mod tests {
                 last_data: false,
                 sequence_number: None,
                 data: outgoing_unmasked,
+                relayed_from_opt: None,
             })
             .unwrap();
 
@@ -1306,6 +1823,7 @@ mod tests {
                 last_data: false,
                 sequence_number: Some(0),
                 data: b"hello".to_vec(),
+                relayed_from_opt: None,
             })
             .unwrap();
 
@@ -1363,6 +1881,7 @@ mod tests {
                         last_data: false,
                         sequence_number: Some(0),
                         data: b"hello".to_vec(),
+                        relayed_from_opt: None,
                     },
                 })
                 .unwrap();
@@ -1415,6 +1934,7 @@ mod tests {
                         last_data: false,
                         sequence_number: None,
                         data: b"hello".to_vec(),
+                        relayed_from_opt: None,
                     },
                 })
                 .unwrap();
@@ -1445,6 +1965,7 @@ mod tests {
             last_data: false,
             sequence_number: Some(0),
             data: b"hello".to_vec(),
+            relayed_from_opt: None,
         };
         let msg_a = msg.clone();
 
@@ -1544,12 +2065,14 @@ mod tests {
             last_data: false,
             sequence_number: None,
             data: b"hello".to_vec(),
+            relayed_from_opt: None,
         };
         let msg_a = TransmitDataMsg {
             endpoint: Endpoint::Socket(peer_addr.clone()),
             last_data: false,
             sequence_number: None,
             data: b"worlds".to_vec(),
+            relayed_from_opt: None,
         };
         let expected_data = JsonMasquerader::new().mask(&msg_a.data).unwrap();
 
@@ -1659,6 +2182,7 @@ mod tests {
                 last_data: true,
                 sequence_number: Some(0),
                 data: b"hello".to_vec(),
+                relayed_from_opt: None,
             },
         });
 
@@ -1684,6 +2208,66 @@ mod tests {
         );
     }
 
+    #[test]
+    fn relayed_packages_over_their_channels_window_wait_without_holding_up_the_others() {
+        let key = main_cryptde().public_key().clone();
+        let peer_addr = SocketAddr::from_str("3.4.5.6:7890").unwrap();
+        let busy = IpAddr::from_str("4.5.6.7").unwrap();
+        let quiet = IpAddr::from_str("5.6.7.8").unwrap();
+        let unbounded_send_params_arc = Arc::new(Mutex::new(vec![]));
+        let mut subject = StreamHandlerPool::new(vec![]);
+        subject.stream_writers.insert(
+            StreamWriterKey::from(peer_addr),
+            Some(Box::new(
+                SenderWrapperMock::new(peer_addr).unbounded_send_params(&unbounded_send_params_arc),
+            )),
+        );
+        let channel_windows = Arc::new(ChannelWindows::new(1, CHANNEL_HOLD_BYTES));
+        subject
+            .channel_windows
+            .insert(StreamWriterKey::from(peer_addr), channel_windows.clone());
+        let before = metrics::HELD_PACKAGES.get();
+        let mut send = |relayed_from_opt: Option<IpAddr>, data: &[u8]| {
+            subject.handle_dispatcher_node_query_response(DispatcherNodeQueryResponse {
+                result: Some(NodeQueryResponseMetadata::new(
+                    key.clone(),
+                    Some(NodeAddr::from(&peer_addr)),
+                    ZERO_RATE_PACK.clone(),
+                )),
+                context: TransmitDataMsg {
+                    endpoint: Endpoint::Key(key.clone()),
+                    last_data: false,
+                    sequence_number: None,
+                    data: data.to_vec(),
+                    relayed_from_opt,
+                },
+            })
+        };
+
+        send(Some(busy), b"busy 1");
+        send(Some(busy), b"busy 2");
+        send(Some(quiet), b"quiet");
+        send(None, b"own");
+        let released = channel_windows.release_next();
+
+        let masquerader = JsonMasquerader::new();
+        let unmask = |packet: &SequencedPacket| {
+            String::from_utf8(masquerader.try_unmask(&packet.data).unwrap().chunk).unwrap()
+        };
+        let sent = unbounded_send_params_arc
+            .lock()
+            .unwrap()
+            .iter()
+            .map(unmask)
+            .collect::<Vec<String>>();
+        assert_eq!(sent, vec!["busy 1", "quiet", "own"]);
+        assert_eq!(
+            released.iter().map(unmask).collect::<Vec<String>>(),
+            vec!["busy 2"]
+        );
+        assert!(metrics::HELD_PACKAGES.get() > before);
+    }
+
     #[test]
     #[should_panic(
         expected = "Neighborhood has returned a NodeDescriptor with no ports. This indicates an unrecoverable error."
@@ -1699,6 +2283,7 @@ mod tests {
             last_data: false,
             sequence_number: None,
             data: b"hello".to_vec(),
+            relayed_from_opt: None,
         };
 
         let system = System::new("test");
@@ -1790,6 +2375,7 @@ mod tests {
                     last_data: false,
                     sequence_number: None,
                     data: hello,
+                    relayed_from_opt: None,
                 })
                 .unwrap();
 
@@ -1800,6 +2386,7 @@ mod tests {
                     last_data: false,
                     sequence_number: None,
                     data: worlds,
+                    relayed_from_opt: None,
                 })
                 .unwrap();
 
@@ -1864,6 +2451,7 @@ mod tests {
                     last_data: false,
                     sequence_number: None,
                     data: b"hello".to_vec(),
+                    relayed_from_opt: None,
                 })
                 .unwrap();
 
@@ -1913,6 +2501,7 @@ mod tests {
                 last_data: false,
                 sequence_number: Some(0),
                 data: outgoing_unmasked,
+                relayed_from_opt: None,
             })
             .unwrap();
 
@@ -1925,4 +2514,51 @@ mod tests {
             1000,
         );
     }
+
+    #[test]
+    fn stream_handler_pool_punches_a_hole_and_adds_the_resulting_stream() {
+        init_test_logging();
+        let listener = std::net::TcpListener::bind(SocketAddr::new(localhost(), 0)).unwrap();
+        let peer_addr = listener.local_addr().unwrap();
+        let local_port = hole_punch::pick_local_port().unwrap();
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let system = System::new("stream_handler_pool_punches_a_hole");
+            let subject = StreamHandlerPool::new(vec![Box::new(JsonDiscriminatorFactory::new())]);
+            let subject_addr: Addr<StreamHandlerPool> = subject.start();
+            let subject_subs = StreamHandlerPool::make_subs_from(&subject_addr);
+            let peer_actors = peer_actors_builder().build();
+            subject_subs
+                .bind
+                .try_send(PoolBindMessage {
+                    dispatcher_subs: peer_actors.dispatcher,
+                    stream_handler_pool_subs: subject_subs.clone(),
+                    neighborhood_subs: peer_actors.neighborhood,
+                })
+                .unwrap();
+
+            tx.send(subject_subs).unwrap();
+
+            system.run();
+        });
+        let subject_subs = rx.recv().unwrap();
+
+        subject_subs
+            .punch_hole_sub
+            .try_send(PunchHoleMsg {
+                peer_addr,
+                local_port,
+            })
+            .unwrap();
+
+        let (_stream, remote_addr) = listener.accept().unwrap();
+        assert_eq!(remote_addr.port(), local_port);
+        TestLogHandler::new().await_log_containing(
+            &format!(
+                "Setting up clandestine StreamReader with reception_port Some({}) on 127.0.0.1:{} to listen to {}",
+                local_port, local_port, peer_addr
+            ),
+            1000,
+        );
+    }
 }
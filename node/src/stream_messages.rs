@@ -7,6 +7,7 @@ use crate::sub_lib::stream_connector::ConnectionInfo;
 use actix::{Message, Recipient};
 use std::fmt;
 use std::fmt::Debug;
+use std::fmt::Display;
 use std::fmt::Formatter;
 use std::net::SocketAddr;
 
@@ -35,6 +36,8 @@ impl AddStreamMsg {
 pub struct NonClandestineAttributes {
     pub reception_port: u16,
     pub sequence_number: u64,
+    // The client closed only its sending half, and may still be waiting for the rest of the response
+    pub half_closed: bool,
 }
 
 #[derive(PartialEq, Debug, Clone)]
@@ -43,11 +46,31 @@ pub enum RemovedStreamType {
     NonClandestine(NonClandestineAttributes),
 }
 
+// Why a stream went away, for the connection audit log
+#[derive(PartialEq, Debug, Clone)]
+pub enum DisconnectReason {
+    PeerClosed,
+    StreamError(String),
+    ConnectFailed(String),
+}
+
+impl Display for DisconnectReason {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            DisconnectReason::PeerClosed => write!(f, "closed by peer"),
+            DisconnectReason::StreamError(e) => write!(f, "stream error: {}", e),
+            DisconnectReason::ConnectFailed(e) => write!(f, "connection failed: {}", e),
+        }
+    }
+}
+
 #[derive(PartialEq, Message)]
 pub struct RemoveStreamMsg {
     pub local_addr: SocketAddr,
     pub peer_addr: SocketAddr,
     pub stream_type: RemovedStreamType,
+    pub bytes_read: u64,
+    pub reason: DisconnectReason,
     pub sub: Recipient<StreamShutdownMsg>,
 }
 
@@ -55,8 +78,8 @@ impl Debug for RemoveStreamMsg {
     fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
         write!(
             f,
-            "RemoveStreamMsg {{ peer_addr: {}, local_addr: {}, stream_type: {:?}, sub: <unprintable> }}",
-            self.peer_addr, self.local_addr, self.stream_type
+            "RemoveStreamMsg {{ peer_addr: {}, local_addr: {}, stream_type: {:?}, bytes_read: {}, reason: {:?}, sub: <unprintable> }}",
+            self.peer_addr, self.local_addr, self.stream_type, self.bytes_read, self.reason
         )
     }
 }
@@ -97,4 +120,17 @@ mod tests {
 
         assert_eq!(result, String::from("PoolBindMessage"));
     }
+
+    #[test]
+    fn disconnect_reasons_are_displayed_for_the_audit_log() {
+        assert_eq!(DisconnectReason::PeerClosed.to_string(), "closed by peer");
+        assert_eq!(
+            DisconnectReason::StreamError("Connection reset".to_string()).to_string(),
+            "stream error: Connection reset"
+        );
+        assert_eq!(
+            DisconnectReason::ConnectFailed("Connection refused".to_string()).to_string(),
+            "connection failed: Connection refused"
+        );
+    }
 }
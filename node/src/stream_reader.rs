@@ -24,6 +24,7 @@ pub struct StreamReaderReal {
     stream_shutdown_sub: Recipient<StreamShutdownMsg>,
     discriminators: Vec<Discriminator>,
     is_clandestine: bool,
+    bytes_read: u64,
     logger: Logger,
     sequencer: Sequencer,
 }
@@ -44,7 +45,7 @@ impl Future for StreamReaderReal {
                         "Stream {} has shut down (0-byte read)",
                         Self::stringify(self.local_addr, self.peer_addr)
                     );
-                    self.shutdown();
+                    self.shutdown(true, DisconnectReason::PeerClosed);
                     return Ok(Async::Ready(()));
                 }
                 Ok(Async::Ready(length)) => {
@@ -54,6 +55,7 @@ impl Future for StreamReaderReal {
                         length,
                         Self::stringify(self.local_addr, self.peer_addr)
                     );
+                    self.bytes_read += length as u64;
                     self.wrangle_discriminators(&buf, length)
                 }
                 Err(e) => {
@@ -64,7 +66,7 @@ impl Future for StreamReaderReal {
                             Self::stringify(self.local_addr, self.peer_addr),
                             e
                         );
-                        self.shutdown();
+                        self.shutdown(false, DisconnectReason::StreamError(e.to_string()));
                         return Err(());
                     } else {
                         // TODO this could be exploitable and inefficient: if we keep getting non-dead-stream errors, we go into a tight loop and do not return
@@ -112,6 +114,7 @@ impl StreamReaderReal {
             stream_shutdown_sub,
             discriminators,
             is_clandestine,
+            bytes_read: 0,
             logger: Logger::new(&name),
             sequencer: Sequencer::new(),
         }
@@ -178,7 +181,7 @@ impl StreamReaderReal {
         }
     }
 
-    fn shutdown(&mut self) {
+    fn shutdown(&mut self, half_closed: bool, reason: DisconnectReason) {
         debug!(self.logger, "Directing removal of {}clandestine StreamReader with reception_port {:?} on {} listening to {}", if self.is_clandestine {""} else {"non-"}, self.reception_port, self.local_addr, self.peer_addr);
         self.remove_sub
             .try_send(RemoveStreamMsg {
@@ -192,8 +195,11 @@ impl StreamReaderReal {
                             "Non-clandestine StreamReader should always have a reception_port",
                         ),
                         sequence_number: self.sequencer.next_sequence_number(),
+                        half_closed,
                     })
                 },
+                bytes_read: self.bytes_read,
+                reason,
                 sub: self.stream_shutdown_sub.clone(),
             })
             .expect("StreamHandlerPool is dead");
@@ -283,6 +289,8 @@ mod tests {
                 peer_addr,
                 local_addr,
                 stream_type: RemovedStreamType::Clandestine,
+                bytes_read: 0,
+                reason: DisconnectReason::PeerClosed,
                 sub: dispatcher_subs.stream_shutdown_sub,
             }
         );
@@ -332,6 +340,8 @@ mod tests {
                 peer_addr,
                 local_addr,
                 stream_type: RemovedStreamType::Clandestine,
+                bytes_read: 0,
+                reason: DisconnectReason::StreamError("broken pipe".to_string()),
                 sub: dispatcher_subs.stream_shutdown_sub,
             }
         );
@@ -493,6 +503,10 @@ mod tests {
 
         subject.poll().err();
 
+        assert_eq!(
+            subject.bytes_read,
+            (partial_request.len() + remaining_request.len()) as u64
+        );
         System::current().stop_with_code(0);
         system.run();
 
@@ -714,7 +728,12 @@ mod tests {
             local_addr,
         );
 
-        subject.shutdown();
+        subject.bytes_read = 4321;
+
+        subject.shutdown(
+            false,
+            DisconnectReason::StreamError("timed out".to_string()),
+        );
 
         System::current().stop_with_code(0);
         system.run();
@@ -726,6 +745,8 @@ mod tests {
                 peer_addr,
                 local_addr,
                 stream_type: RemovedStreamType::Clandestine,
+                bytes_read: 4321,
+                reason: DisconnectReason::StreamError("timed out".to_string()),
                 sub: dispatcher_subs.stream_shutdown_sub,
             }
         );
@@ -754,7 +775,7 @@ mod tests {
         );
         subject.sequencer.next_sequence_number(); // just so it's not 0
 
-        subject.shutdown();
+        subject.shutdown(false, DisconnectReason::PeerClosed);
 
         System::current().stop_with_code(0);
         system.run();
@@ -768,7 +789,56 @@ mod tests {
                 stream_type: NonClandestine(NonClandestineAttributes {
                     reception_port: HTTP_PORT,
                     sequence_number: 1,
+                    half_closed: false,
+                }),
+                bytes_read: 0,
+                reason: DisconnectReason::PeerClosed,
+                sub: dispatcher_subs.stream_shutdown_sub,
+            }
+        );
+    }
+
+    #[test]
+    fn zero_byte_read_from_non_clandestine_stream_is_reported_as_half_close() {
+        let (shp_recording_arc, stream_handler_pool_subs) = stream_handler_pool_stuff();
+        let (_, dispatcher_subs) = dispatcher_stuff();
+        let peer_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
+        let system = System::new("test");
+        let local_addr = SocketAddr::from_str("1.2.3.5:6789").unwrap();
+        let discriminator_factories: Vec<Box<dyn DiscriminatorFactory>> =
+            vec![Box::new(JsonDiscriminatorFactory::new())];
+        let reader = ReadHalfWrapperMock::new().poll_read_result(vec![], Ok(Async::Ready(0)));
+        let mut subject = StreamReaderReal::new(
+            Box::new(reader),
+            Some(HTTP_PORT),
+            dispatcher_subs.ibcd_sub,
+            stream_handler_pool_subs.remove_sub,
+            dispatcher_subs.stream_shutdown_sub.clone(),
+            discriminator_factories,
+            false,
+            peer_addr,
+            local_addr,
+        );
+
+        let result = subject.poll();
+
+        assert_eq!(result, Ok(Async::Ready(())));
+        System::current().stop_with_code(0);
+        system.run();
+        let shp_recording = shp_recording_arc.lock().unwrap();
+        let remove_stream_msg = shp_recording.get_record::<RemoveStreamMsg>(0);
+        assert_eq!(
+            remove_stream_msg,
+            &RemoveStreamMsg {
+                peer_addr,
+                local_addr,
+                stream_type: NonClandestine(NonClandestineAttributes {
+                    reception_port: HTTP_PORT,
+                    sequence_number: 0,
+                    half_closed: true,
                 }),
+                bytes_read: 0,
+                reason: DisconnectReason::PeerClosed,
                 sub: dispatcher_subs.stream_shutdown_sub,
             }
         );
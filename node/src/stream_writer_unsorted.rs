@@ -1,17 +1,26 @@
+use crate::sub_lib::channel_windows::ChannelWindows;
 use crate::sub_lib::channel_wrappers::ReceiverWrapper;
 use crate::sub_lib::logger::Logger;
+use crate::sub_lib::path_mtu;
 use crate::sub_lib::sequence_buffer::SequencedPacket;
 use crate::sub_lib::tokio_wrappers::WriteHalfWrapper;
 use crate::sub_lib::utils::indicates_dead_stream;
+use std::collections::VecDeque;
+use std::io::ErrorKind;
 use std::net::SocketAddr;
+use std::sync::Arc;
 use tokio::prelude::Async;
 use tokio::prelude::Future;
 
 pub struct StreamWriterUnsorted {
     stream: Box<dyn WriteHalfWrapper>,
     rx_to_write: Box<dyn ReceiverWrapper<SequencedPacket>>,
+    peer_addr: SocketAddr,
     logger: Logger,
     buf: Option<SequencedPacket>,
+    channel_windows_opt: Option<Arc<ChannelWindows>>,
+    // Packages the channel windows have let go of, which go out before any more are read
+    released: VecDeque<SequencedPacket>,
 }
 
 impl Future for StreamWriterUnsorted {
@@ -20,7 +29,7 @@ impl Future for StreamWriterUnsorted {
 
     fn poll(&mut self) -> Result<Async<<Self as Future>::Item>, <Self as Future>::Error> {
         loop {
-            match self.buf.take() {
+            match self.buf.take().or_else(|| self.released.pop_front()) {
                 None => {
                     self.buf = match self.rx_to_write.poll() {
                         Ok(Async::Ready(Some(data))) => Some(data),
@@ -47,6 +56,9 @@ impl Future for StreamWriterUnsorted {
                                     packet.data.len(),
                                     e
                                 );
+                                if e.kind() == ErrorKind::TimedOut {
+                                    path_mtu::report_black_hole(self.peer_addr, &self.logger);
+                                }
                                 return Err(());
                             } else {
                                 self.buf = Some(packet);
@@ -72,6 +84,8 @@ impl Future for StreamWriterUnsorted {
                                     packet.sequence_number,
                                     false,
                                 ));
+                            } else if let Some(channel_windows) = &self.channel_windows_opt {
+                                self.released.extend(channel_windows.release_next());
                             }
                         }
                         Ok(Async::NotReady) => {
@@ -96,22 +110,31 @@ impl StreamWriterUnsorted {
         StreamWriterUnsorted {
             stream,
             rx_to_write,
+            peer_addr,
             logger,
             buf: None,
+            channel_windows_opt: None,
+            released: VecDeque::new(),
         }
     }
+
+    pub fn with_channel_windows(mut self, channel_windows: Arc<ChannelWindows>) -> Self {
+        self.channel_windows_opt = Some(channel_windows);
+        self
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::sub_lib::channel_windows::ChannelOffer;
     use crate::test_utils::channel_wrapper_mocks::ReceiverWrapperMock;
     use crate::test_utils::logging::init_test_logging;
     use crate::test_utils::logging::TestLogHandler;
     use crate::test_utils::tokio_wrapper_mocks::WriteHalfWrapperMock;
     use std::io;
     use std::io::ErrorKind;
-    use std::net::SocketAddr;
+    use std::net::{IpAddr, SocketAddr};
     use std::str::FromStr;
 
     #[test]
@@ -185,6 +208,28 @@ mod tests {
         assert_eq!(write_params.lock().unwrap().len(), 1);
     }
 
+    #[test]
+    fn stream_writer_that_times_out_lowers_the_path_mtu_for_its_neighbor() {
+        init_test_logging();
+        let mut rx = Box::new(ReceiverWrapperMock::new());
+        rx.poll_results = vec![Ok(Async::Ready(Some(SequencedPacket::new(
+            b"hello".to_vec(),
+            0,
+            false,
+        ))))];
+        let writer = WriteHalfWrapperMock::new()
+            .poll_write_result(Err(io::Error::from(ErrorKind::TimedOut)));
+        let peer_addr = SocketAddr::from_str("2.3.4.5:6789").unwrap();
+        let mut subject = StreamWriterUnsorted::new(Box::new(writer), peer_addr, rx);
+
+        let result = subject.poll();
+
+        assert_eq!(result.is_ok(), false);
+        TestLogHandler::new().exists_log_containing(
+            "WARN: StreamWriter for 2.3.4.5:6789: Stream to 2.3.4.5:6789 timed out in mid-transmission; assuming a path MTU of 1492 from now on",
+        );
+    }
+
     #[test]
     fn stream_writer_logs_error_and_continues_when_it_gets_a_non_dead_stream_error() {
         init_test_logging();
@@ -402,4 +447,56 @@ mod tests {
         );
         assert_eq!(write_params.lock().unwrap().get(2).unwrap(), &b"s".to_vec());
     }
+
+    #[test]
+    fn stream_writer_sends_what_a_written_package_releases_before_reading_more() {
+        let relayer = IpAddr::from_str("2.3.4.5").unwrap();
+        let channel_windows = Arc::new(ChannelWindows::new(10, 100));
+        let first = match channel_windows.offer(
+            Some(relayer),
+            SequencedPacket::new(b"worlds".to_vec(), 0, false),
+        ) {
+            ChannelOffer::Send(packet) => packet,
+            offer => panic!("Expected to send, but got {:?}", offer),
+        };
+        let held = channel_windows.offer(
+            Some(relayer),
+            SequencedPacket::new(b"hello".to_vec(), 0, false),
+        );
+        let mut rx = Box::new(ReceiverWrapperMock::new());
+        rx.poll_results = vec![
+            Ok(Async::Ready(Some(first))),
+            Ok(Async::Ready(Some(SequencedPacket::new(
+                b"own".to_vec(),
+                0,
+                false,
+            )))),
+            Ok(Async::NotReady),
+        ];
+        let writer = WriteHalfWrapperMock::new()
+            .poll_write_result(Ok(Async::Ready(3)))
+            .poll_write_result(Ok(Async::Ready(3)))
+            .poll_write_result(Ok(Async::Ready(5)))
+            .poll_write_result(Ok(Async::Ready(3)));
+        let write_params = writer.poll_write_params.clone();
+        let peer_addr = SocketAddr::from_str("1.2.3.4:5678").unwrap();
+        let mut subject = StreamWriterUnsorted::new(Box::new(writer), peer_addr, rx)
+            .with_channel_windows(channel_windows.clone());
+
+        let result = subject.poll();
+
+        assert_eq!(result, Ok(Async::NotReady));
+        assert_eq!(held, ChannelOffer::Held);
+        assert_eq!(
+            *write_params.lock().unwrap(),
+            vec![
+                b"worlds".to_vec(),
+                b"lds".to_vec(),
+                b"hello".to_vec(),
+                b"own".to_vec()
+            ]
+        );
+        assert_eq!(channel_windows.held_count(relayer), 0);
+        assert_eq!(channel_windows.waiting_bytes(relayer), 0);
+    }
 }
@@ -1,5 +1,6 @@
 // Copyright (c) 2017-2019, Substratum LLC (https://substratum.net) and/or its affiliates. All rights reserved.
 use crate::accountant::{ReceivedPayments, SentPayments};
+use crate::sub_lib::hopper::ExpiredCoresPackage;
 use crate::sub_lib::peer_actors::{BindMessage, StartMessage};
 use crate::sub_lib::wallet::Wallet;
 use actix::Message;
@@ -34,6 +35,8 @@ pub struct AccountantSubs {
     pub report_exit_service_consumed: Recipient<ReportExitServiceConsumedMessage>,
     pub report_new_payments: Recipient<ReceivedPayments>,
     pub report_sent_payments: Recipient<SentPayments>,
+    pub report_earning_wallet_change: Recipient<ReportEarningWalletChangeMessage>,
+    pub payment_overdue_from_hopper: Recipient<ExpiredCoresPackage<PaymentOverdue_0v1>>,
     pub ui_message_sub: Recipient<NodeFromUiMessage>,
 }
 
@@ -75,6 +78,26 @@ pub struct ReportExitServiceConsumedMessage {
     pub byte_rate: u64,
 }
 
+// A Node we pay has announced, over its own signature, that it now earns into a different wallet
+#[derive(Clone, PartialEq, Debug, Message)]
+pub struct ReportEarningWalletChangeMessage {
+    pub previous_wallet: Wallet,
+    pub current_wallet: Wallet,
+    pub effective_block: u64,
+}
+
+// Sent to a delinquent Node before it's banned: an honest operator whose consuming wallet is
+// misconfigured gets a chance to fix it and pay up. balance is in gwub, like the receivable's.
+#[derive(Clone, PartialEq, Debug, Message, Serialize, Deserialize)]
+#[allow(non_camel_case_types)]
+pub struct PaymentOverdue_0v1 {
+    pub creditor: Wallet,
+    pub debtor: Wallet,
+    pub balance: i64,
+    pub age_sec: u64,
+    pub ban_in_sec: u64,
+}
+
 #[derive(Clone, PartialEq, Debug, Message)]
 pub struct GetFinancialStatisticsMessage {
     pub client_id: u64,
@@ -112,6 +135,11 @@ mod tests {
             report_exit_service_consumed: recipient!(recorder, ReportExitServiceConsumedMessage),
             report_new_payments: recipient!(recorder, ReceivedPayments),
             report_sent_payments: recipient!(recorder, SentPayments),
+            report_earning_wallet_change: recipient!(recorder, ReportEarningWalletChangeMessage),
+            payment_overdue_from_hopper: recipient!(
+                recorder,
+                ExpiredCoresPackage<PaymentOverdue_0v1>
+            ),
             ui_message_sub: recipient!(recorder, NodeFromUiMessage),
         };
 
@@ -0,0 +1,355 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use crate::sub_lib::cryptde::{CryptDE, CryptData, PlainData, PublicKey};
+use crate::sub_lib::wallet::Wallet;
+use crate::trust_dao::{TrustRecord, TRUST_CACHE};
+use lazy_static::lazy_static;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+// Most attestations a Node may publish at once; a record with more is ignored altogether
+pub const MAX_PUBLISHED_ATTESTATIONS: usize = 16;
+// How often a Node publishes its attestations, and how often it's listened to when it does
+pub const ATTESTATION_INTERVAL: Duration = Duration::from_secs(60 * 60);
+// However much one attestor has seen of a wallet, it counts for no more payments than this
+pub const MAX_ATTESTED_PAYMENTS: u64 = 10;
+
+lazy_static! {
+    pub static ref ATTESTATION_CACHE: AttestationCache = AttestationCache::default();
+}
+
+// What one Node has seen of the payments a wallet owed it, signed with its key so that the Nodes
+// that read it in Gossip know who's saying it
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Attestation {
+    pub subject: Wallet,
+    pub paid_on_time: u32,
+    pub overdue: u32,
+    pub signature: CryptData,
+}
+
+impl Attestation {
+    pub fn new(
+        subject: Wallet,
+        paid_on_time: u32,
+        overdue: u32,
+        attestor: &Wallet,
+        cryptde: &dyn CryptDE,
+    ) -> Self {
+        let signature = cryptde
+            .sign(&Self::signed_data(
+                &subject,
+                paid_on_time,
+                overdue,
+                attestor,
+            ))
+            .expect("Couldn't sign attestation");
+        Attestation {
+            subject,
+            paid_on_time,
+            overdue,
+            signature,
+        }
+    }
+
+    pub fn is_signed(
+        &self,
+        cryptde: &dyn CryptDE,
+        public_key: &PublicKey,
+        attestor: &Wallet,
+    ) -> bool {
+        cryptde.verify_signature(
+            &Self::signed_data(&self.subject, self.paid_on_time, self.overdue, attestor),
+            &self.signature,
+            public_key,
+        )
+    }
+
+    fn signed_data(
+        subject: &Wallet,
+        paid_on_time: u32,
+        overdue: u32,
+        attestor: &Wallet,
+    ) -> PlainData {
+        PlainData::from(
+            format!(
+                "{} attests that {} paid on time {} times and late {} times",
+                attestor, subject, paid_on_time, overdue
+            )
+            .into_bytes(),
+        )
+    }
+}
+
+// The wallets with the most payment evidence first, leaving out the attestor's own
+pub fn make_attestations(
+    records: Vec<(Wallet, TrustRecord)>,
+    attestor: &Wallet,
+    cryptde: &dyn CryptDE,
+) -> Vec<Attestation> {
+    let attestor = attestor.as_address_wallet();
+    let mut records = records
+        .into_iter()
+        .filter(|(wallet, record)| {
+            wallet.as_address_wallet() != attestor
+                && record.payments_on_time + record.payments_overdue > 0
+        })
+        .collect::<Vec<_>>();
+    records.sort_by_key(|(wallet, record)| {
+        (
+            std::cmp::Reverse(record.payments_on_time + record.payments_overdue),
+            wallet.to_string(),
+        )
+    });
+    records
+        .into_iter()
+        .take(MAX_PUBLISHED_ATTESTATIONS)
+        .map(|(wallet, record)| {
+            Attestation::new(
+                wallet.as_address_wallet(),
+                record.payments_on_time.min(u32::MAX as u64) as u32,
+                record.payments_overdue.min(u32::MAX as u64) as u32,
+                &attestor,
+                cryptde,
+            )
+        })
+        .collect()
+}
+
+// What other Nodes attest a wallet paid, with one voice per attesting wallet
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct AttestedPayments {
+    pub attestors: u64,
+    pub paid_on_time: u64,
+    pub overdue: u64,
+}
+
+// Written by the Neighborhood, which has vetted the attestations, and read by the Accountant.
+// Attestations are kept by their attestor's earning wallet, so an operator running many Nodes
+// into one wallet still has only one voice.
+#[derive(Default)]
+pub struct AttestationCache {
+    attestations: RwLock<HashMap<Wallet, (Instant, Vec<Attestation>)>>,
+}
+
+impl AttestationCache {
+    // Replaces the attestor's earlier attestations, unless they were taken less than
+    // ATTESTATION_INTERVAL ago. Returns whether they were replaced.
+    pub fn accept(&self, attestor: &Wallet, attestations: Vec<Attestation>, now: Instant) -> bool {
+        let mut cache = self
+            .attestations
+            .write()
+            .expect("Failed to accept attestations");
+        let attestor = attestor.as_address_wallet();
+        if let Some((taken_at, _)) = cache.get(&attestor) {
+            if now.saturating_duration_since(*taken_at) < ATTESTATION_INTERVAL {
+                return false;
+            }
+        }
+        let mut subjects = vec![];
+        let attestations = attestations
+            .into_iter()
+            .filter(|attestation| {
+                let subject = attestation.subject.as_address_wallet();
+                if subject == attestor || subjects.contains(&subject) {
+                    false
+                } else {
+                    subjects.push(subject);
+                    true
+                }
+            })
+            .collect();
+        cache.insert(attestor, (now, attestations));
+        true
+    }
+
+    pub fn attested_payments(&self, subject: &Wallet) -> AttestedPayments {
+        let subject = subject.as_address_wallet();
+        self.attestations
+            .read()
+            .expect("Failed to read attestations")
+            .values()
+            .filter_map(|(_, attestations)| {
+                attestations
+                    .iter()
+                    .find(|attestation| attestation.subject.as_address_wallet() == subject)
+            })
+            .fold(AttestedPayments::default(), |so_far, attestation| {
+                AttestedPayments {
+                    attestors: so_far.attestors + 1,
+                    paid_on_time: so_far.paid_on_time
+                        + (attestation.paid_on_time as u64).min(MAX_ATTESTED_PAYMENTS),
+                    overdue: so_far.overdue
+                        + (attestation.overdue as u64).min(MAX_ATTESTED_PAYMENTS),
+                }
+            })
+    }
+}
+
+// The trust score a wallet would have if the payments other Nodes attest to were this Node's own
+// experience. A wallet this Node has a payment record of its own for keeps the score it earned.
+pub fn score_with_attestations(wallet: &Wallet) -> u8 {
+    let record = TRUST_CACHE.record(wallet).unwrap_or_default();
+    if record.payments_on_time + record.payments_overdue > 0 {
+        return record.score();
+    }
+    let attested = ATTESTATION_CACHE.attested_payments(wallet);
+    TrustRecord {
+        payments_on_time: attested.paid_on_time,
+        payments_overdue: attested.overdue,
+        ..record
+    }
+    .score()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sub_lib::cryptde_null::CryptDENull;
+    use crate::test_utils::make_wallet;
+    use masq_lib::test_utils::utils::DEFAULT_CHAIN_ID;
+
+    fn record(payments_on_time: u64, payments_overdue: u64) -> TrustRecord {
+        TrustRecord {
+            payments_on_time,
+            payments_overdue,
+            ..TrustRecord::default()
+        }
+    }
+
+    #[test]
+    fn attestations_are_signed_only_for_their_own_attestor_and_key() {
+        let cryptde = CryptDENull::new(DEFAULT_CHAIN_ID);
+        let other_cryptde = CryptDENull::new(DEFAULT_CHAIN_ID);
+        let attestor = make_wallet("attestor");
+
+        let subject = Attestation::new(make_wallet("payer"), 5, 1, &attestor, &cryptde);
+
+        assert_eq!(
+            subject.is_signed(&cryptde, cryptde.public_key(), &attestor),
+            true
+        );
+        assert_eq!(
+            subject.is_signed(&cryptde, other_cryptde.public_key(), &attestor),
+            false
+        );
+        assert_eq!(
+            subject.is_signed(&cryptde, cryptde.public_key(), &make_wallet("impostor")),
+            false
+        );
+        let inflated = Attestation {
+            paid_on_time: 500,
+            ..subject.clone()
+        };
+        assert_eq!(
+            inflated.is_signed(&cryptde, cryptde.public_key(), &attestor),
+            false
+        );
+        let serialized = serde_cbor::ser::to_vec(&subject).unwrap();
+        assert_eq!(
+            serde_cbor::de::from_slice::<Attestation>(&serialized).unwrap(),
+            subject
+        );
+    }
+
+    #[test]
+    fn attestations_are_made_for_the_wallets_with_the_most_payments() {
+        let cryptde = CryptDENull::new(DEFAULT_CHAIN_ID);
+        let attestor = make_wallet("attestor");
+        let mut records = (0..20)
+            .map(|n| (make_wallet(&format!("payer {}", n)), record(n, 1)))
+            .collect::<Vec<_>>();
+        records.push((attestor.clone(), record(1000, 0)));
+        records.push((make_wallet("stranger"), record(0, 0)));
+
+        let result = make_attestations(records, &attestor, &cryptde);
+
+        assert_eq!(result.len(), MAX_PUBLISHED_ATTESTATIONS);
+        assert_eq!(result[0].subject, make_wallet("payer 19"));
+        assert_eq!((result[0].paid_on_time, result[0].overdue), (19, 1));
+        assert_eq!(result[15].subject, make_wallet("payer 4"));
+        assert!(result.iter().all(|attestation| attestation.is_signed(
+            &cryptde,
+            cryptde.public_key(),
+            &attestor
+        )));
+    }
+
+    #[test]
+    fn each_attestor_has_one_capped_voice_and_is_heard_at_most_once_an_interval() {
+        let cryptde = CryptDENull::new(DEFAULT_CHAIN_ID);
+        let subject = AttestationCache::default();
+        let payer = make_wallet("payer");
+        let attest = |attestor: &Wallet, paid_on_time: u32, overdue: u32| {
+            Attestation::new(payer.clone(), paid_on_time, overdue, attestor, &cryptde)
+        };
+        let honest = make_wallet("honest");
+        let booster = make_wallet("booster");
+        let now = Instant::now();
+
+        let first = subject.accept(&honest, vec![attest(&honest, 3, 1)], now);
+        let boosted = subject.accept(
+            &booster,
+            vec![attest(&booster, 1000, 0), attest(&booster, 1000, 0)],
+            now,
+        );
+        let too_soon = subject.accept(&honest, vec![attest(&honest, 0, 9)], now);
+        let self_praise = subject.accept(
+            &payer,
+            vec![Attestation::new(payer.clone(), 1000, 0, &payer, &cryptde)],
+            now,
+        );
+
+        assert_eq!(
+            (first, boosted, too_soon, self_praise),
+            (true, true, false, true)
+        );
+        assert_eq!(
+            subject.attested_payments(&payer),
+            AttestedPayments {
+                attestors: 2,
+                paid_on_time: 3 + MAX_ATTESTED_PAYMENTS,
+                overdue: 1,
+            }
+        );
+        let later = subject.accept(
+            &honest,
+            vec![attest(&honest, 0, 9)],
+            now + ATTESTATION_INTERVAL,
+        );
+        assert_eq!(later, true);
+        assert_eq!(
+            subject.attested_payments(&payer),
+            AttestedPayments {
+                attestors: 2,
+                paid_on_time: MAX_ATTESTED_PAYMENTS,
+                overdue: 9,
+            }
+        );
+    }
+
+    #[test]
+    fn attestations_only_score_wallets_without_a_payment_record_of_their_own() {
+        let cryptde = CryptDENull::new(DEFAULT_CHAIN_ID);
+        let newcomer = make_wallet("attested newcomer");
+        let known = make_wallet("attested known payer");
+        let attestor = make_wallet("attestor of newcomers");
+        TRUST_CACHE.insert(&known, record(0, 2));
+        ATTESTATION_CACHE.accept(
+            &attestor,
+            vec![
+                Attestation::new(newcomer.clone(), 10, 0, &attestor, &cryptde),
+                Attestation::new(known.clone(), 10, 0, &attestor, &cryptde),
+            ],
+            Instant::now(),
+        );
+
+        assert_eq!(score_with_attestations(&newcomer), 71);
+        assert_eq!(score_with_attestations(&known), TRUST_CACHE.score(&known));
+        assert_eq!(
+            score_with_attestations(&make_wallet("unattested newcomer")),
+            TRUST_CACHE.score(&make_wallet("unattested newcomer"))
+        );
+    }
+}
@@ -3,6 +3,7 @@
 use crate::accountant::payable_dao::{PayableAccount, Payment};
 use crate::blockchain::blockchain_bridge::RetrieveTransactions;
 use crate::blockchain::blockchain_interface::BlockchainResult;
+use crate::blockchain::payment_adapter::PaymentToken;
 use crate::sub_lib::peer_actors::BindMessage;
 use actix::Message;
 use actix::Recipient;
@@ -14,7 +15,13 @@ use std::fmt::{Debug, Formatter};
 pub struct BlockchainBridgeConfig {
     pub blockchain_service_url: Option<String>,
     pub chain_id: u8,
+    // Where to ask for test funds, on a testnet
+    pub faucet_url_opt: Option<String>,
     pub gas_price: u64,
+    // None to settle in the chain's MASQ token
+    pub payment_token_opt: Option<PaymentToken>,
+    // Where to ask what a MASQ token is worth when the day's balances are recorded
+    pub price_feed_url_opt: Option<String>,
 }
 
 #[derive(Clone)]
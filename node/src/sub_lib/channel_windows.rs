@@ -0,0 +1,249 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use crate::sub_lib::sequence_buffer::SequencedPacket;
+use std::collections::{HashMap, VecDeque};
+use std::net::IpAddr;
+use std::sync::Mutex;
+
+// How much of one neighbor's relayed traffic can be waiting to go out on a connection
+pub const CHANNEL_WINDOW_BYTES: usize = 256 * 1024;
+// How much of it can be held back behind that window before the rest is dropped
+pub const CHANNEL_HOLD_BYTES: usize = 4 * CHANNEL_WINDOW_BYTES;
+
+// Every route through a neighbor shares the one connection to it, so the packages waiting on that
+// connection are divided into channels by the neighbor that handed them to this Node. A channel
+// that has a window's worth waiting has the rest of its packages held back until the writer has
+// sent some of it, so one busy neighbor can't bury everybody else's traffic. This Node's own
+// traffic (None) always gets room. A channel that already has its hold limit held back loses
+// whatever else it offers, so a neighbor that won't slow down can't make this Node buffer without
+// end; the senders' own retransmission has to cope with that, as it does with loss. Shared between StreamHandlerPool, which offers packages, and
+// the connection's writer, which releases them in the order it writes them.
+pub struct ChannelWindows {
+    window: usize,
+    hold_limit: usize,
+    inner: Mutex<ChannelWindowsInner>,
+}
+
+#[derive(Default)]
+struct ChannelWindowsInner {
+    waiting_bytes: HashMap<IpAddr, usize>,
+    held: HashMap<IpAddr, VecDeque<SequencedPacket>>,
+    held_bytes: HashMap<IpAddr, usize>,
+    in_order: VecDeque<(Option<IpAddr>, usize)>,
+}
+
+impl ChannelWindowsInner {
+    fn has_room(&self, channel: IpAddr, len: usize, window: usize) -> bool {
+        match self.waiting_bytes.get(&channel) {
+            // A package bigger than the window still goes out on its own
+            Some(waiting) => waiting + len <= window,
+            None => true,
+        }
+    }
+
+    fn admit(&mut self, channel_opt: Option<IpAddr>, len: usize) {
+        if let Some(channel) = channel_opt {
+            *self.waiting_bytes.entry(channel).or_insert(0) += len;
+        }
+        self.in_order.push_back((channel_opt, len));
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ChannelOffer {
+    Send(SequencedPacket),
+    Held,
+    Dropped,
+}
+
+impl ChannelWindows {
+    pub fn new(window: usize, hold_limit: usize) -> ChannelWindows {
+        ChannelWindows {
+            window,
+            hold_limit,
+            inner: Mutex::new(ChannelWindowsInner::default()),
+        }
+    }
+
+    // Says whether the package can go to the writer now, is held for release_next, or is dropped
+    pub fn offer(&self, channel_opt: Option<IpAddr>, packet: SequencedPacket) -> ChannelOffer {
+        let mut inner = self.inner.lock().expect("ChannelWindows is poisoned");
+        if let Some(channel) = channel_opt {
+            let len = packet.data.len();
+            let already_holding = inner.held.contains_key(&channel);
+            if already_holding || !inner.has_room(channel, len, self.window) {
+                let held_bytes = inner.held_bytes.get(&channel).cloned().unwrap_or(0);
+                if held_bytes + len > self.hold_limit {
+                    return ChannelOffer::Dropped;
+                }
+                inner.held_bytes.insert(channel, held_bytes + len);
+                inner.held.entry(channel).or_default().push_back(packet);
+                return ChannelOffer::Held;
+            }
+        }
+        inner.admit(channel_opt, packet.data.len());
+        ChannelOffer::Send(packet)
+    }
+
+    // Called each time the writer has finished with a package. Returns the held packages that
+    // fit now, which the writer must send before anything else so that the order still holds.
+    pub fn release_next(&self) -> Vec<SequencedPacket> {
+        let mut inner = self.inner.lock().expect("ChannelWindows is poisoned");
+        let channel = match inner.in_order.pop_front() {
+            Some((Some(channel), len)) => {
+                let now_empty = match inner.waiting_bytes.get_mut(&channel) {
+                    Some(waiting) => {
+                        *waiting -= len;
+                        *waiting == 0
+                    }
+                    None => false,
+                };
+                if now_empty {
+                    inner.waiting_bytes.remove(&channel);
+                }
+                channel
+            }
+            _ => return vec![],
+        };
+        let mut released = vec![];
+        while let Some(len) = inner
+            .held
+            .get(&channel)
+            .and_then(|held| held.front())
+            .map(|packet| packet.data.len())
+        {
+            if !inner.has_room(channel, len, self.window) {
+                break;
+            }
+            let held = inner
+                .held
+                .get_mut(&channel)
+                .expect("Held packages disappeared");
+            let packet = held.pop_front().expect("Held package disappeared");
+            if held.is_empty() {
+                inner.held.remove(&channel);
+                inner.held_bytes.remove(&channel);
+            } else if let Some(held_bytes) = inner.held_bytes.get_mut(&channel) {
+                *held_bytes -= len;
+            }
+            *inner.waiting_bytes.entry(channel).or_insert(0) += len;
+            released.push(packet);
+        }
+        // They go out next, ahead of whatever the writer hasn't read yet
+        released.iter().rev().for_each(|packet| {
+            inner
+                .in_order
+                .push_front((Some(channel), packet.data.len()))
+        });
+        released
+    }
+
+    pub fn waiting_bytes(&self, channel: IpAddr) -> usize {
+        self.inner
+            .lock()
+            .expect("ChannelWindows is poisoned")
+            .waiting_bytes
+            .get(&channel)
+            .cloned()
+            .unwrap_or(0)
+    }
+
+    pub fn held_count(&self, channel: IpAddr) -> usize {
+        self.inner
+            .lock()
+            .expect("ChannelWindows is poisoned")
+            .held
+            .get(&channel)
+            .map(|held| held.len())
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn packet(len: usize) -> SequencedPacket {
+        SequencedPacket::new(vec![0; len], 0, false)
+    }
+
+    #[test]
+    fn a_full_channel_is_held_back_while_the_others_still_get_room() {
+        let busy = IpAddr::from_str("1.2.3.4").unwrap();
+        let quiet = IpAddr::from_str("2.3.4.5").unwrap();
+        let subject = ChannelWindows::new(100, 1000);
+
+        let first = subject.offer(Some(busy), packet(60));
+        let second = subject.offer(Some(busy), packet(40));
+        let third = subject.offer(Some(busy), packet(1));
+        let quiet_result = subject.offer(Some(quiet), packet(100));
+        let own_result = subject.offer(None, packet(1000));
+
+        assert_eq!(first, ChannelOffer::Send(packet(60)));
+        assert_eq!(second, ChannelOffer::Send(packet(40)));
+        assert_eq!(third, ChannelOffer::Held);
+        assert_eq!(quiet_result, ChannelOffer::Send(packet(100)));
+        assert_eq!(own_result, ChannelOffer::Send(packet(1000)));
+        assert_eq!(subject.waiting_bytes(busy), 100);
+        assert_eq!(subject.held_count(busy), 1);
+        assert_eq!(subject.waiting_bytes(quiet), 100);
+    }
+
+    #[test]
+    fn writing_a_package_releases_what_its_channel_held_back_in_order() {
+        let busy = IpAddr::from_str("1.2.3.4").unwrap();
+        let subject = ChannelWindows::new(100, 1000);
+        subject.offer(None, packet(10));
+        subject.offer(Some(busy), packet(60));
+        subject.offer(Some(busy), packet(40));
+        subject.offer(Some(busy), packet(30));
+        subject.offer(Some(busy), packet(20));
+
+        let after_own = subject.release_next();
+        let late_offer = subject.offer(Some(busy), packet(11));
+        let after_first = subject.release_next();
+        let after_second = subject.release_next();
+
+        assert_eq!(after_own, vec![]);
+        assert_eq!(late_offer, ChannelOffer::Held);
+        assert_eq!(after_first, vec![packet(30), packet(20)]);
+        assert_eq!(after_second, vec![packet(11)]);
+        assert_eq!(subject.waiting_bytes(busy), 71);
+        assert_eq!(subject.held_count(busy), 0);
+    }
+
+    #[test]
+    fn a_package_bigger_than_the_window_goes_out_on_an_empty_channel() {
+        let busy = IpAddr::from_str("1.2.3.4").unwrap();
+        let subject = ChannelWindows::new(100, 1000);
+
+        let result = subject.offer(Some(busy), packet(150));
+
+        assert_eq!(result, ChannelOffer::Send(packet(150)));
+        subject.release_next();
+        assert_eq!(subject.waiting_bytes(busy), 0);
+    }
+
+    #[test]
+    fn a_channel_that_has_its_hold_limit_held_back_loses_what_else_it_offers() {
+        let busy = IpAddr::from_str("1.2.3.4").unwrap();
+        let quiet = IpAddr::from_str("2.3.4.5").unwrap();
+        let subject = ChannelWindows::new(100, 60);
+        subject.offer(Some(busy), packet(100));
+        subject.offer(Some(busy), packet(30));
+        subject.offer(Some(busy), packet(25));
+
+        let over_the_limit = subject.offer(Some(busy), packet(10));
+        let quiet_result = subject.offer(Some(quiet), packet(100));
+        let own_result = subject.offer(None, packet(1000));
+        let released = subject.release_next();
+        let after_release = subject.offer(Some(busy), packet(50));
+
+        assert_eq!(over_the_limit, ChannelOffer::Dropped);
+        assert_eq!(quiet_result, ChannelOffer::Send(packet(100)));
+        assert_eq!(own_result, ChannelOffer::Send(packet(1000)));
+        assert_eq!(released, vec![packet(30), packet(25)]);
+        assert_eq!(after_release, ChannelOffer::Held);
+        assert_eq!(subject.held_count(busy), 1);
+    }
+}
@@ -0,0 +1,331 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use crate::sub_lib::cryptde::{CryptdecError, PublicKey};
+use crate::sub_lib::peer_table::PeerTable;
+use lazy_static::lazy_static;
+use sodiumoxide::crypto::aead::chacha20poly1305_ietf as aead;
+use sodiumoxide::crypto::box_ as encryption;
+use sodiumoxide::crypto::hash::sha256;
+use sodiumoxide::crypto::scalarmult::curve25519;
+use sodiumoxide::crypto::sealedbox;
+use sodiumoxide::randombytes::randombytes_into;
+use std::collections::BTreeSet;
+use std::sync::RwLock;
+
+// Feature flag in a node record announcing that the Node can open data sealed with this suite
+pub const X25519_CHACHA20_POLY1305: &str = "cipher:x25519-chacha20poly1305";
+
+// Everybody understands this one, so nobody needs to announce it
+pub const X25519_XSALSA20_POLY1305: &str = "cipher:x25519-xsalsa20poly1305";
+
+lazy_static! {
+    // Most preferred first; the last one must be the suite every Node understands
+    static ref CIPHER_SUITES: Vec<Box<dyn CipherSuite>> = vec![
+        Box::new(X25519ChaCha20Poly1305 {}),
+        Box::new(X25519XSalsa20Poly1305 {}),
+    ];
+    static ref PEER_FEATURES: RwLock<PeerTable<BTreeSet<String>>> =
+        RwLock::new(PeerTable::default());
+}
+
+// A way to seal data so that only the holder of the secret half of an X25519 key can open it.
+// Every suite uses the same keys, so the choice of suite never changes a Node's public key.
+pub trait CipherSuite: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn seal(
+        &self,
+        recipient: &encryption::PublicKey,
+        data: &[u8],
+    ) -> Result<Vec<u8>, CryptdecError>;
+    fn open(
+        &self,
+        public_key: &encryption::PublicKey,
+        secret_key: &encryption::SecretKey,
+        data: &[u8],
+    ) -> Option<Vec<u8>>;
+}
+
+// The feature flags this Node should advertise in its node record
+pub fn local_features() -> BTreeSet<String> {
+    CIPHER_SUITES
+        .iter()
+        .map(|suite| suite.name())
+        .filter(|name| *name != X25519_XSALSA20_POLY1305)
+        .map(|name| name.to_string())
+        .collect()
+}
+
+// Called whenever a node record we believe arrives, so that data for that Node can be sealed with
+// the best suite it says it understands
+pub fn record_peer_features(public_key: &PublicKey, features: &BTreeSet<String>) {
+    let mut peer_features = PEER_FEATURES
+        .write()
+        .expect("Peer feature table is poisoned");
+    if features.is_empty() {
+        peer_features.remove(public_key);
+    } else {
+        peer_features.insert(public_key, features.clone());
+    }
+}
+
+// Called when the Node's record leaves the database
+pub fn forget_peer_features(public_key: &PublicKey) {
+    PEER_FEATURES
+        .write()
+        .expect("Peer feature table is poisoned")
+        .remove(public_key);
+}
+
+pub fn peer_has_feature(public_key: &PublicKey, feature: &str) -> bool {
+    PEER_FEATURES
+        .read()
+        .expect("Peer feature table is poisoned")
+        .get(public_key)
+        .map(|features| features.contains(feature))
+        .unwrap_or(false)
+}
+
+pub fn suite_for(public_key: &PublicKey) -> &'static dyn CipherSuite {
+    let peer_features = PEER_FEATURES
+        .read()
+        .expect("Peer feature table is poisoned");
+    let features_opt = peer_features.get(public_key);
+    CIPHER_SUITES
+        .iter()
+        .find(|suite| {
+            suite.name() == X25519_XSALSA20_POLY1305
+                || features_opt
+                    .map(|features| features.contains(suite.name()))
+                    .unwrap_or(false)
+        })
+        .expect("No universal cipher suite")
+        .as_ref()
+}
+
+// Sealed data doesn't say which suite sealed it, so each is tried in turn; all of them are
+// authenticated, so only the right one can succeed
+pub fn open_with_any_suite(
+    public_key: &encryption::PublicKey,
+    secret_key: &encryption::SecretKey,
+    data: &[u8],
+) -> Option<Vec<u8>> {
+    CIPHER_SUITES
+        .iter()
+        .find_map(|suite| suite.open(public_key, secret_key, data))
+}
+
+pub struct X25519XSalsa20Poly1305 {}
+
+impl CipherSuite for X25519XSalsa20Poly1305 {
+    fn name(&self) -> &'static str {
+        X25519_XSALSA20_POLY1305
+    }
+
+    fn seal(
+        &self,
+        recipient: &encryption::PublicKey,
+        data: &[u8],
+    ) -> Result<Vec<u8>, CryptdecError> {
+        Ok(sealedbox::seal(data, recipient))
+    }
+
+    fn open(
+        &self,
+        public_key: &encryption::PublicKey,
+        secret_key: &encryption::SecretKey,
+        data: &[u8],
+    ) -> Option<Vec<u8>> {
+        sealedbox::open(data, public_key, secret_key).ok()
+    }
+}
+
+// Sealed data is an ephemeral X25519 public key, then a nonce, then the ChaCha20-Poly1305
+// ciphertext under a key hashed from the ephemeral and recipient keys and their shared secret
+pub struct X25519ChaCha20Poly1305 {}
+
+impl CipherSuite for X25519ChaCha20Poly1305 {
+    fn name(&self) -> &'static str {
+        X25519_CHACHA20_POLY1305
+    }
+
+    // A peer's key comes from Gossip, so one that's a low-order point is refused, not trusted
+    fn seal(
+        &self,
+        recipient: &encryption::PublicKey,
+        data: &[u8],
+    ) -> Result<Vec<u8>, CryptdecError> {
+        let mut ephemeral_secret = curve25519::Scalar([0u8; curve25519::SCALARBYTES]);
+        randombytes_into(&mut ephemeral_secret.0);
+        let ephemeral_public = curve25519::scalarmult_base(&ephemeral_secret);
+        let shared =
+            curve25519::scalarmult(&ephemeral_secret, &curve25519::GroupElement(recipient.0))
+                .map_err(|_| CryptdecError::InvalidKey(format!("{:?}", recipient.0)))?;
+        let key = Self::derive_key(&shared.0, &ephemeral_public.0, &recipient.0);
+        let mut nonce = aead::Nonce([0u8; aead::NONCEBYTES]);
+        randombytes_into(&mut nonce.0);
+        let mut result = ephemeral_public.0.to_vec();
+        result.extend_from_slice(&nonce.0);
+        result.extend(aead::seal(data, Some(&ephemeral_public.0), &nonce, &key));
+        Ok(result)
+    }
+
+    fn open(
+        &self,
+        public_key: &encryption::PublicKey,
+        secret_key: &encryption::SecretKey,
+        data: &[u8],
+    ) -> Option<Vec<u8>> {
+        let nonce_start = curve25519::GROUPELEMENTBYTES;
+        let ciphertext_start = nonce_start + aead::NONCEBYTES;
+        if data.len() < ciphertext_start + aead::TAGBYTES {
+            return None;
+        }
+        let mut ephemeral_public = curve25519::GroupElement([0u8; curve25519::GROUPELEMENTBYTES]);
+        ephemeral_public.0.copy_from_slice(&data[..nonce_start]);
+        let nonce = aead::Nonce::from_slice(&data[nonce_start..ciphertext_start])?;
+        let shared =
+            curve25519::scalarmult(&curve25519::Scalar(secret_key.0), &ephemeral_public).ok()?;
+        let key = Self::derive_key(&shared.0, &ephemeral_public.0, &public_key.0);
+        aead::open(
+            &data[ciphertext_start..],
+            Some(&ephemeral_public.0),
+            &nonce,
+            &key,
+        )
+        .ok()
+    }
+}
+
+impl X25519ChaCha20Poly1305 {
+    fn derive_key(shared: &[u8], ephemeral_public: &[u8], recipient: &[u8]) -> aead::Key {
+        let digest = sha256::hash(&[shared, ephemeral_public, recipient].concat());
+        aead::Key(digest.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_recipient() -> (encryption::PublicKey, encryption::SecretKey) {
+        let _ = sodiumoxide::init();
+        encryption::gen_keypair()
+    }
+
+    fn make_node_key(encryption_public_key: &encryption::PublicKey) -> PublicKey {
+        let mut bytes = encryption_public_key.0.to_vec();
+        bytes.extend_from_slice(&[0u8; 32]);
+        PublicKey::from(bytes)
+    }
+
+    #[test]
+    fn every_suite_opens_what_it_seals_and_nothing_else() {
+        let (public_key, secret_key) = make_recipient();
+        let (other_public_key, other_secret_key) = make_recipient();
+        let data = b"Aside from that, Mrs. Lincoln, how was the play?";
+
+        CIPHER_SUITES.iter().for_each(|suite| {
+            let sealed = suite.seal(&public_key, data).unwrap();
+
+            assert_eq!(
+                suite.open(&public_key, &secret_key, &sealed),
+                Some(data.to_vec()),
+                "{}",
+                suite.name()
+            );
+            assert_eq!(
+                suite.open(&other_public_key, &other_secret_key, &sealed),
+                None,
+                "{}",
+                suite.name()
+            );
+        });
+    }
+
+    #[test]
+    fn tampered_chacha20_poly1305_data_does_not_open() {
+        let (public_key, secret_key) = make_recipient();
+        let subject = X25519ChaCha20Poly1305 {};
+        let mut sealed = subject.seal(&public_key, b"booga").unwrap();
+        let last = sealed.len() - 1;
+        sealed[last] ^= 1;
+
+        let result = subject.open(&public_key, &secret_key, &sealed);
+
+        assert_eq!(result, None);
+        assert_eq!(subject.open(&public_key, &secret_key, &[0u8; 59]), None);
+    }
+
+    #[test]
+    fn chacha20_poly1305_refuses_to_seal_for_a_low_order_key() {
+        let _ = sodiumoxide::init();
+        let subject = X25519ChaCha20Poly1305 {};
+
+        let result = subject.seal(&encryption::PublicKey([0u8; 32]), b"booga");
+
+        assert_eq!(
+            result,
+            Err(CryptdecError::InvalidKey(format!("{:?}", [0u8; 32])))
+        );
+    }
+
+    #[test]
+    fn data_sealed_with_any_suite_can_be_opened_without_knowing_which() {
+        let (public_key, secret_key) = make_recipient();
+
+        CIPHER_SUITES.iter().for_each(|suite| {
+            let sealed = suite.seal(&public_key, b"booga").unwrap();
+
+            assert_eq!(
+                open_with_any_suite(&public_key, &secret_key, &sealed),
+                Some(b"booga".to_vec())
+            );
+        });
+    }
+
+    #[test]
+    fn local_features_announce_everything_but_the_universal_suite() {
+        let result = local_features();
+
+        assert_eq!(
+            result.into_iter().collect::<Vec<String>>(),
+            vec![X25519_CHACHA20_POLY1305.to_string()]
+        );
+    }
+
+    #[test]
+    fn suite_for_picks_the_best_suite_the_peer_has_announced() {
+        let (public_key, _) = make_recipient();
+        let node_key = make_node_key(&public_key);
+
+        assert_eq!(suite_for(&node_key).name(), X25519_XSALSA20_POLY1305);
+
+        record_peer_features(&node_key, &local_features());
+        assert_eq!(suite_for(&node_key).name(), X25519_CHACHA20_POLY1305);
+
+        record_peer_features(&node_key, &BTreeSet::new());
+        assert_eq!(suite_for(&node_key).name(), X25519_XSALSA20_POLY1305);
+    }
+
+    #[test]
+    fn forgotten_peers_get_the_universal_suite() {
+        let (public_key, _) = make_recipient();
+        let node_key = make_node_key(&public_key);
+        record_peer_features(&node_key, &local_features());
+
+        forget_peer_features(&node_key);
+
+        assert_eq!(suite_for(&node_key).name(), X25519_XSALSA20_POLY1305);
+        assert_eq!(peer_has_feature(&node_key, X25519_CHACHA20_POLY1305), false);
+    }
+
+    #[test]
+    fn suite_for_ignores_features_it_does_not_understand() {
+        let (public_key, _) = make_recipient();
+        let node_key = make_node_key(&public_key);
+        let features = vec!["cipher:rot13".to_string()].into_iter().collect();
+
+        record_peer_features(&node_key, &features);
+
+        assert_eq!(suite_for(&node_key).name(), X25519_XSALSA20_POLY1305);
+    }
+}
@@ -0,0 +1,171 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use masq_lib::shared_schema::ConfiguratorError;
+use serde_json::{json, Value};
+use std::io::Write;
+
+// Goes up whenever the layout of --output json changes in a way that could break a wrapper
+pub const OUTPUT_SCHEMA_VERSION: u64 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputFormat {
+    Human,
+    Json,
+}
+
+impl OutputFormat {
+    // clap has vetted the value by the time this is asked, but an error from clap itself has to be
+    // reported in the right format too, so this looks at the raw arguments
+    pub fn from_args(args: &[String]) -> OutputFormat {
+        let value_opt = args.iter().enumerate().find_map(|(index, arg)| {
+            if arg == "--output" {
+                args.get(index + 1).map(|value| value.as_str())
+            } else {
+                arg.strip_prefix("--output=")
+            }
+        });
+        match value_opt {
+            Some(value) if value.eq_ignore_ascii_case("json") => OutputFormat::Json,
+            _ => OutputFormat::Human,
+        }
+    }
+}
+
+pub fn success(mode: &str, result: Value) -> String {
+    to_string(json!({
+        "version": OUTPUT_SCHEMA_VERSION,
+        "mode": mode,
+        "result": result,
+    }))
+}
+
+// Every error has a code a wrapper can match on and a message a person can read; errors about
+// particular parameters say which, and what kind of trouble each is in
+pub fn failure(
+    mode: &str,
+    code: &str,
+    message: &str,
+    error_opt: Option<&ConfiguratorError>,
+) -> String {
+    let parameters = match error_opt {
+        Some(error) => error
+            .param_errors
+            .iter()
+            .map(|param_error| {
+                json!({
+                    "parameter": param_error.parameter,
+                    "code": param_error.code,
+                    "reason": param_error.reason,
+                })
+            })
+            .collect::<Vec<Value>>(),
+        None => vec![],
+    };
+    to_string(json!({
+        "version": OUTPUT_SCHEMA_VERSION,
+        "mode": mode,
+        "error": {
+            "code": code,
+            "message": message,
+            "parameters": parameters,
+        },
+    }))
+}
+
+pub fn configuration_failure(mode: &str, error: &ConfiguratorError) -> String {
+    failure(mode, "configuration", "Configuration error", Some(error))
+}
+
+pub fn write(target: &mut dyn Write, output: &str) {
+    writeln!(target, "{}", output).expect("writeln! failed");
+    target.flush().expect("flush failed");
+}
+
+fn to_string(value: Value) -> String {
+    serde_json::to_string_pretty(&value).expect("Couldn't serialize output to JSON")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use masq_lib::shared_schema::{ParamError, ParamErrorCode};
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn output_format_is_found_in_either_form_and_defaults_to_human() {
+        assert_eq!(
+            OutputFormat::from_args(&args(&["MASQNode", "--dump-config", "--output", "json"])),
+            OutputFormat::Json
+        );
+        assert_eq!(
+            OutputFormat::from_args(&args(&["MASQNode", "--output=JSON", "--dump-config"])),
+            OutputFormat::Json
+        );
+        assert_eq!(
+            OutputFormat::from_args(&args(&["MASQNode", "--output", "human"])),
+            OutputFormat::Human
+        );
+        assert_eq!(
+            OutputFormat::from_args(&args(&["MASQNode", "--dump-config", "--output"])),
+            OutputFormat::Human
+        );
+        assert_eq!(
+            OutputFormat::from_args(&args(&["MASQNode", "--dump-config"])),
+            OutputFormat::Human
+        );
+    }
+
+    #[test]
+    fn success_carries_the_version_the_mode_and_the_result() {
+        let result = success("dumpConfig", json!({"gasPrice": "1"}));
+
+        let value: Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "version": OUTPUT_SCHEMA_VERSION,
+                "mode": "dumpConfig",
+                "result": {"gasPrice": "1"},
+            })
+        );
+    }
+
+    #[test]
+    fn configuration_failure_names_each_troubled_parameter() {
+        let error = ConfiguratorError::new(vec![
+            ParamError::new("chain", "Unknown chain"),
+            ParamError::with_code("word-count", ParamErrorCode::Required, "Must be supplied"),
+        ]);
+
+        let result = configuration_failure("generateWallet", &error);
+
+        let value: Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "version": OUTPUT_SCHEMA_VERSION,
+                "mode": "generateWallet",
+                "error": {
+                    "code": "configuration",
+                    "message": "Configuration error",
+                    "parameters": [
+                        {"parameter": "chain", "code": "Invalid", "reason": "Unknown chain"},
+                        {"parameter": "word-count", "code": "Required", "reason": "Must be supplied"},
+                    ],
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn failure_without_a_configurator_error_has_no_parameters() {
+        let result = failure("collectDiagnostics", "io", "Disk full", None);
+
+        let value: Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(value["error"]["code"], json!("io"));
+        assert_eq!(value["error"]["message"], json!("Disk full"));
+        assert_eq!(value["error"]["parameters"], json!([]));
+    }
+}
@@ -0,0 +1,62 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use std::fmt::Debug;
+use std::time::{Duration, SystemTime};
+
+// Where the actors that keep accounts get the time. Payment thresholds and delinquency bans play
+// out over weeks; with a virtual clock in place of this one, a test can make the weeks pass in
+// milliseconds.
+pub trait Clock: Debug + Send + Sync {
+    fn now(&self) -> SystemTime;
+
+    // How long a timer has to wait in real time for interval to pass on this clock
+    fn real_interval(&self, interval: Duration) -> Duration {
+        interval
+    }
+
+    // Never negative: a timestamp from this clock's future is zero time ago
+    fn elapsed_since(&self, earlier: SystemTime) -> Duration {
+        self.now()
+            .duration_since(earlier)
+            .unwrap_or_else(|_| Duration::new(0, 0))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ClockReal {}
+
+impl Clock for ClockReal {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn real_clock_tells_the_system_time() {
+        let subject = ClockReal {};
+        let before = SystemTime::now();
+
+        let result = subject.now();
+
+        let after = SystemTime::now();
+        assert!(before <= result && result <= after);
+        assert_eq!(
+            subject.real_interval(Duration::from_secs(60)),
+            Duration::from_secs(60)
+        );
+    }
+
+    #[test]
+    fn time_since_a_future_timestamp_is_zero() {
+        let subject = ClockReal {};
+
+        let past = subject.elapsed_since(SystemTime::now() - Duration::from_secs(100));
+        let future = subject.elapsed_since(SystemTime::now() + Duration::from_secs(100));
+
+        assert!(past >= Duration::from_secs(100));
+        assert_eq!(future, Duration::new(0, 0));
+    }
+}
@@ -0,0 +1,339 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use crate::sub_lib::logger::Logger;
+use std::net::{ToSocketAddrs, UdpSocket};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub const DEFAULT_CLOCK_SKEW_TOLERANCE_SECS: u32 = 120;
+pub const DEFAULT_TIME_SERVERS: &[&str] = &["pool.ntp.org", "time.cloudflare.com"];
+pub const CLOCK_SKEW_CHECK_INTERVAL: Duration = Duration::from_secs(3600);
+const NTP_PORT: u16 = 123;
+const NTP_PACKET_LEN: usize = 48;
+const NTP_TIMEOUT: Duration = Duration::from_secs(5);
+// From the start of 1900, where NTP counts from, to the start of 1970
+const NTP_EPOCH_OFFSET_SECS: i64 = 2_208_988_800;
+
+// How far apart two clocks may be before either of them is treated as wrong; set once from
+// --clock-skew-tolerance before anything compares timestamps from other Nodes with ours
+static TOLERANCE_SECS: AtomicU32 = AtomicU32::new(DEFAULT_CLOCK_SKEW_TOLERANCE_SECS);
+
+pub fn set_tolerance_secs(seconds: u32) {
+    TOLERANCE_SECS.store(seconds, Ordering::Relaxed)
+}
+
+pub fn tolerance_secs() -> u32 {
+    TOLERANCE_SECS.load(Ordering::Relaxed)
+}
+
+pub trait TimeServerQuerier: Send {
+    // How many milliseconds the server's clock is ahead of ours; negative if it's behind
+    fn query(&self, server: &str) -> Result<i64, String>;
+}
+
+pub struct TimeServerQuerierReal {}
+
+impl TimeServerQuerier for TimeServerQuerierReal {
+    fn query(&self, server: &str) -> Result<i64, String> {
+        let address = if server.contains(':') {
+            server.to_string()
+        } else {
+            format!("{}:{}", server, NTP_PORT)
+        };
+        let socket_addr = address
+            .to_socket_addrs()
+            .map_err(|e| format!("Couldn't look up {}: {}", server, e))?
+            .next()
+            .ok_or_else(|| format!("{} has no address", server))?;
+        let bind_addr = if socket_addr.is_ipv4() {
+            "0.0.0.0:0"
+        } else {
+            "[::]:0"
+        };
+        let socket = UdpSocket::bind(bind_addr)
+            .and_then(|socket| socket.set_read_timeout(Some(NTP_TIMEOUT)).map(|_| socket))
+            .map_err(|e| format!("Couldn't open a socket to ask {}: {}", server, e))?;
+        let sent_at = SystemTime::now();
+        socket
+            .send_to(&sntp_request(), socket_addr)
+            .map_err(|e| format!("Couldn't ask {} for the time: {}", server, e))?;
+        let mut reply = [0u8; NTP_PACKET_LEN];
+        let len = socket
+            .recv(&mut reply)
+            .map_err(|e| format!("{} didn't say what time it is: {}", server, e))?;
+        let received_at = SystemTime::now();
+        offset_from_reply(&reply[..len], sent_at, received_at)
+            .map_err(|e| format!("{} {}", server, e))
+    }
+}
+
+// Version 3 client request; the server fills in its own timestamps and ignores the rest
+pub fn sntp_request() -> [u8; NTP_PACKET_LEN] {
+    let mut request = [0u8; NTP_PACKET_LEN];
+    request[0] = 0x1B;
+    request
+}
+
+// The usual SNTP estimate, which is exact if the request and the reply took equally long on the way
+pub fn offset_from_reply(
+    reply: &[u8],
+    sent_at: SystemTime,
+    received_at: SystemTime,
+) -> Result<i64, String> {
+    if reply.len() < NTP_PACKET_LEN {
+        return Err(format!("sent a {}-byte reply", reply.len()));
+    }
+    if reply[0] & 0x07 != 4 {
+        return Err("sent something other than a server reply".to_string());
+    }
+    if reply[1] == 0 {
+        return Err("refused to say what time it is".to_string());
+    }
+    let server_received = ntp_millis(&reply[32..40]);
+    let server_sent = ntp_millis(&reply[40..48]);
+    Ok(((server_received - unix_millis(sent_at)) + (server_sent - unix_millis(received_at))) / 2)
+}
+
+fn ntp_millis(timestamp: &[u8]) -> i64 {
+    let seconds = u32::from_be_bytes([timestamp[0], timestamp[1], timestamp[2], timestamp[3]]);
+    let fraction = u32::from_be_bytes([timestamp[4], timestamp[5], timestamp[6], timestamp[7]]);
+    (i64::from(seconds) - NTP_EPOCH_OFFSET_SECS) * 1000
+        + ((u64::from(fraction) * 1000) >> 32) as i64
+}
+
+fn unix_millis(time: SystemTime) -> i64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(since) => since.as_millis() as i64,
+        Err(e) => -(e.duration().as_millis() as i64),
+    }
+}
+
+pub struct ClockSkewChecker {
+    servers: Vec<String>,
+    querier: Box<dyn TimeServerQuerier>,
+    logger: Logger,
+}
+
+impl ClockSkewChecker {
+    pub fn new(servers: Vec<String>) -> Self {
+        Self {
+            servers,
+            querier: Box::new(TimeServerQuerierReal {}),
+            logger: Logger::new("ClockSkewChecker"),
+        }
+    }
+
+    // The skew according to the first server that answers, in milliseconds
+    pub fn check(&self) -> Option<i64> {
+        let mut errors = vec![];
+        for server in &self.servers {
+            match self.querier.query(server) {
+                Ok(skew_millis) => {
+                    self.report(server, skew_millis);
+                    return Some(skew_millis);
+                }
+                Err(e) => errors.push(e),
+            }
+        }
+        warning!(
+            self.logger,
+            "Couldn't compare this computer's clock with network time: {}",
+            errors.join("; ")
+        );
+        None
+    }
+
+    fn report(&self, server: &str, skew_millis: i64) {
+        let skew_secs = skew_millis.abs() / 1000;
+        let direction = if skew_millis > 0 {
+            "behind"
+        } else {
+            "ahead of"
+        };
+        if skew_secs > i64::from(tolerance_secs()) {
+            warning!(
+                self.logger,
+                "This computer's clock is {} seconds {} network time, according to {}. That's more \
+                 than the --clock-skew-tolerance of {} seconds, so other Nodes will refuse some of \
+                 this Node's traffic and the times on its payments will be wrong. Set the clock from \
+                 network time: turn on automatic time synchronization in your operating system's \
+                 date and time settings.",
+                skew_secs,
+                direction,
+                server,
+                tolerance_secs()
+            );
+        } else {
+            debug!(
+                self.logger,
+                "This computer's clock is {} ms {} network time, according to {}",
+                skew_millis.abs(),
+                direction,
+                server
+            );
+        }
+    }
+
+    fn run(self) {
+        loop {
+            self.check();
+            thread::sleep(CLOCK_SKEW_CHECK_INTERVAL);
+        }
+    }
+}
+
+// Checks now and every CLOCK_SKEW_CHECK_INTERVAL afterward, unless there are no servers to ask
+pub fn start_clock_skew_checker(servers: Vec<String>) {
+    if servers.is_empty() {
+        return;
+    }
+    let checker = ClockSkewChecker::new(servers);
+    thread::spawn(move || checker.run());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::logging::{init_test_logging, TestLogHandler};
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    struct TimeServerQuerierMock {
+        query_params: Arc<Mutex<Vec<String>>>,
+        query_results: HashMap<String, Result<i64, String>>,
+    }
+
+    impl TimeServerQuerier for TimeServerQuerierMock {
+        fn query(&self, server: &str) -> Result<i64, String> {
+            self.query_params.lock().unwrap().push(server.to_string());
+            self.query_results
+                .get(server)
+                .cloned()
+                .unwrap_or_else(|| panic!("No result for {}", server))
+        }
+    }
+
+    fn reply(server_received: SystemTime, server_sent: SystemTime) -> Vec<u8> {
+        let ntp = |time: SystemTime| {
+            let since = time.duration_since(UNIX_EPOCH).unwrap();
+            let seconds = (since.as_secs() as i64 + NTP_EPOCH_OFFSET_SECS) as u32;
+            let fraction = ((u64::from(since.subsec_millis()) << 32) / 1000) as u32 + 1;
+            let mut bytes = seconds.to_be_bytes().to_vec();
+            bytes.extend_from_slice(&fraction.to_be_bytes());
+            bytes
+        };
+        let mut result = vec![0x1C, 2];
+        result.resize(32, 0);
+        result.extend(ntp(server_received));
+        result.extend(ntp(server_sent));
+        result
+    }
+
+    #[test]
+    fn skew_is_measured_from_the_middle_of_the_round_trip() {
+        let sent_at = UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+        let received_at = sent_at + Duration::from_millis(200);
+        let ahead = reply(
+            sent_at + Duration::from_millis(30_100),
+            sent_at + Duration::from_millis(30_150),
+        );
+        let behind = reply(
+            sent_at - Duration::from_millis(9_900),
+            sent_at - Duration::from_millis(9_850),
+        );
+
+        let ahead_result = offset_from_reply(&ahead, sent_at, received_at);
+        let behind_result = offset_from_reply(&behind, sent_at, received_at);
+
+        assert_eq!(ahead_result, Ok(30_025));
+        assert_eq!(behind_result, Ok(-9_975));
+        assert_eq!(sntp_request()[0], 0x1B);
+    }
+
+    #[test]
+    fn replies_that_arent_answers_are_refused() {
+        let now = SystemTime::now();
+        let mut from_a_client = reply(now, now);
+        from_a_client[0] = 0x1B;
+        let mut kiss_of_death = reply(now, now);
+        kiss_of_death[1] = 0;
+
+        assert_eq!(
+            offset_from_reply(&[0x1C; 20], now, now),
+            Err("sent a 20-byte reply".to_string())
+        );
+        assert_eq!(
+            offset_from_reply(&from_a_client, now, now),
+            Err("sent something other than a server reply".to_string())
+        );
+        assert_eq!(
+            offset_from_reply(&kiss_of_death, now, now),
+            Err("refused to say what time it is".to_string())
+        );
+    }
+
+    #[test]
+    fn skew_beyond_the_tolerance_is_warned_about_with_a_hint() {
+        init_test_logging();
+        let query_params_arc = Arc::new(Mutex::new(vec![]));
+        let subject = ClockSkewChecker {
+            servers: vec!["unreachable.ntp".to_string(), "skewed.ntp".to_string()],
+            querier: Box::new(TimeServerQuerierMock {
+                query_params: query_params_arc.clone(),
+                query_results: vec![
+                    (
+                        "unreachable.ntp".to_string(),
+                        Err("unreachable.ntp didn't say what time it is".to_string()),
+                    ),
+                    (
+                        "skewed.ntp".to_string(),
+                        Ok(-(i64::from(tolerance_secs()) + 1) * 1000),
+                    ),
+                ]
+                .into_iter()
+                .collect(),
+            }),
+            logger: Logger::new("ClockSkewChecker"),
+        };
+
+        let result = subject.check();
+
+        assert_eq!(result, Some(-(i64::from(tolerance_secs()) + 1) * 1000));
+        assert_eq!(
+            *query_params_arc.lock().unwrap(),
+            vec!["unreachable.ntp".to_string(), "skewed.ntp".to_string()]
+        );
+        TestLogHandler::new().exists_log_containing(&format!(
+            "WARN: ClockSkewChecker: This computer's clock is {} seconds ahead of network time, \
+             according to skewed.ntp. That's more than the --clock-skew-tolerance of {} seconds",
+            tolerance_secs() + 1,
+            tolerance_secs()
+        ));
+    }
+
+    #[test]
+    fn no_answer_from_any_server_is_warned_about() {
+        init_test_logging();
+        let subject = ClockSkewChecker {
+            servers: vec!["silent.ntp".to_string()],
+            querier: Box::new(TimeServerQuerierMock {
+                query_params: Arc::new(Mutex::new(vec![])),
+                query_results: vec![(
+                    "silent.ntp".to_string(),
+                    Err("silent.ntp didn't say what time it is: timed out".to_string()),
+                )]
+                .into_iter()
+                .collect(),
+            }),
+            logger: Logger::new("ClockSkewChecker"),
+        };
+
+        let result = subject.check();
+
+        assert_eq!(result, None);
+        TestLogHandler::new().exists_log_containing(
+            "WARN: ClockSkewChecker: Couldn't compare this computer's clock with network time: \
+             silent.ntp didn't say what time it is: timed out",
+        );
+    }
+}
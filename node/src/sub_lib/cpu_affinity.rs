@@ -0,0 +1,92 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+#[cfg(target_os = "linux")]
+use nix::sched::{sched_setaffinity, CpuSet};
+#[cfg(target_os = "linux")]
+use nix::unistd::Pid;
+
+// Parses what --cpu-affinity accepts: cores and ranges of cores, like 0,2-5,7
+pub fn parse_cpu_list(cpus: &str) -> Result<Vec<usize>, String> {
+    let mut result = vec![];
+    for item in cpus.split(',') {
+        let bounds = item
+            .splitn(2, '-')
+            .map(|bound| bound.trim().parse::<usize>())
+            .collect::<Vec<Result<usize, _>>>();
+        match bounds.as_slice() {
+            [Ok(cpu)] => result.push(*cpu),
+            [Ok(low), Ok(high)] if low <= high => result.extend(*low..=*high),
+            _ => {
+                return Err(format!(
+                    "'{}' is not a CPU core or range of CPU cores",
+                    item
+                ))
+            }
+        }
+    }
+    result.sort_unstable();
+    result.dedup();
+    Ok(result)
+}
+
+// Only the calling thread is pinned, but every thread it starts afterward inherits its cores;
+// so this must happen before the actor system starts the threads that do the Node's work
+#[cfg(target_os = "linux")]
+pub fn pin_to_cpus(cpus: &[usize]) -> Result<(), String> {
+    let mut cpu_set = CpuSet::new();
+    for cpu in cpus {
+        cpu_set
+            .set(*cpu)
+            .map_err(|e| format!("Can't pin to CPU core {}: {}", cpu, e))?;
+    }
+    sched_setaffinity(Pid::from_raw(0), &cpu_set)
+        .map_err(|e| format!("Can't pin to CPU cores {:?}: {}", cpus, e))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn pin_to_cpus(_cpus: &[usize]) -> Result<(), String> {
+    Err("CPU affinity is supported only on Linux".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_cpu_list_expands_ranges_and_drops_duplicates() {
+        assert_eq!(parse_cpu_list("3"), Ok(vec![3]));
+        assert_eq!(parse_cpu_list("7,2-5,3"), Ok(vec![2, 3, 4, 5, 7]));
+        assert_eq!(
+            parse_cpu_list("0,5-2"),
+            Err("'5-2' is not a CPU core or range of CPU cores".to_string())
+        );
+        assert_eq!(
+            parse_cpu_list("booga"),
+            Err("'booga' is not a CPU core or range of CPU cores".to_string())
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn pin_to_cpus_refuses_a_core_beyond_what_the_kernel_can_describe() {
+        let result = pin_to_cpus(&[1_000_000]);
+
+        assert_eq!(
+            result
+                .err()
+                .unwrap()
+                .starts_with("Can't pin to CPU core 1000000: "),
+            true
+        );
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    #[test]
+    fn pin_to_cpus_is_refused_off_linux() {
+        let result = pin_to_cpus(&[0]);
+
+        assert_eq!(
+            result,
+            Err("CPU affinity is supported only on Linux".to_string())
+        );
+    }
+}
@@ -7,6 +7,7 @@ use serde::Deserialize;
 use serde::Deserializer;
 use serde::Serialize;
 use serde::Serializer;
+use std::collections::BTreeSet;
 use std::fmt;
 use std::iter::FromIterator;
 use std::str::FromStr;
@@ -553,6 +554,36 @@ pub trait CryptDE: Send + Sync {
         descriptor_fragment: &str,
     ) -> Result<PublicKey, String>;
     fn digest(&self) -> [u8; 32];
+    // Only a CryptDE whose key has been rotated, and whose previous key isn't yet retired, has one
+    fn key_succession_opt(&self) -> Option<KeySuccession> {
+        None
+    }
+    // Node-record feature flags for the cipher suites this CryptDE can open beyond the universal one
+    fn features(&self) -> BTreeSet<String> {
+        BTreeSet::new()
+    }
+    // Present only if this Node has agreed to hybrid post-quantum sealing; goes in the node record
+    fn kem_public_key_opt(&self) -> Option<PlainData> {
+        None
+    }
+}
+
+// The previous key's signature over the current one, so that other Nodes can believe that the
+// Node they knew by the previous key is now using the current one
+#[derive(Clone, Debug, PartialEq, Eq, serde_derive::Serialize, serde_derive::Deserialize)]
+pub struct KeySuccession {
+    pub previous_public_key: PublicKey,
+    pub endorsement: CryptData,
+}
+
+impl KeySuccession {
+    pub fn is_endorsed(&self, cryptde: &dyn CryptDE, current_public_key: &PublicKey) -> bool {
+        cryptde.verify_signature(
+            &PlainData::new(current_public_key.as_slice()),
+            &self.endorsement,
+            &self.previous_public_key,
+        )
+    }
 }
 
 pub struct SerdeCborError {
@@ -572,7 +603,7 @@ impl PartialEq for SerdeCborError {
 }
 
 impl SerdeCborError {
-    fn new(delegate: serde_cbor::error::Error) -> SerdeCborError {
+    pub fn new(delegate: serde_cbor::error::Error) -> SerdeCborError {
         SerdeCborError { delegate }
     }
 }
@@ -584,6 +615,8 @@ pub enum CodexError {
     EncryptionError(CryptdecError),
     DecryptionError(CryptdecError),
     RoutingError(RouteError),
+    // A payload in a form that a later version of the Node knows and this one doesn't
+    UnrecognizedMessageType(String),
 }
 
 pub fn encodex<T>(
@@ -1,17 +1,23 @@
 // Copyright (c) 2017-2019, Substratum LLC (https://substratum.net) and/or its affiliates. All rights reserved.
 use crate::blockchain::blockchain_interface::contract_address;
+use crate::sub_lib::cipher_suite;
 use crate::sub_lib::cryptde;
 use crate::sub_lib::cryptde::{
-    CryptDE, CryptData, CryptdecError, PlainData, PrivateKey, PublicKey, SymmetricKey,
+    CryptDE, CryptData, CryptdecError, KeySuccession, PlainData, PrivateKey, PublicKey,
+    SymmetricKey,
 };
+use crate::sub_lib::deterministic;
+use crate::sub_lib::hybrid_kem;
+use crate::sub_lib::hybrid_kem::KemKeyPair;
 use lazy_static::lazy_static;
 use sodiumoxide::crypto::box_::curve25519xsalsa20poly1305 as cxsp;
 use sodiumoxide::crypto::sealedbox::curve25519blake2bxsalsa20poly1305::SEALBYTES;
-use sodiumoxide::crypto::sealedbox::{open, seal};
 use sodiumoxide::crypto::secretbox;
 use sodiumoxide::crypto::sign as signing;
 use sodiumoxide::crypto::{box_ as encryption, hash};
 use sodiumoxide::randombytes::randombytes_into;
+use std::collections::BTreeSet;
+use std::time::SystemTime;
 
 lazy_static! {
     static ref INITIALIZED: bool = {
@@ -28,6 +34,21 @@ pub struct CryptDEReal {
     signing_secret_key: signing::SecretKey,
     digest: [u8; 32],
     pre_shared_data: [u8; 20],
+    previous_opt: Option<PreviousKey>,
+    kem_key_pair_opt: Option<KemKeyPair>,
+}
+
+// After a key rotation, data can still arrive encrypted with the previous key until it's retired
+struct PreviousKey {
+    cryptde: Box<CryptDEReal>,
+    retire_at: SystemTime,
+    endorsement: CryptData,
+}
+
+impl PreviousKey {
+    fn is_retired(&self) -> bool {
+        SystemTime::now() >= self.retire_at
+    }
 }
 
 impl CryptDE for CryptDEReal {
@@ -36,20 +57,43 @@ impl CryptDE for CryptDEReal {
             return Err(CryptdecError::InvalidKey(format!("{:?}", key.as_slice())));
         }
         let remote_public_key = Self::encryption_public_key_from(key);
-        Ok(CryptData::from(seal(data.as_slice(), &remote_public_key)))
+        if let Some(remote_kem_key) = self.hybrid_kem_key_for(key) {
+            if let Some(sealed) =
+                hybrid_kem::seal(&remote_public_key, &remote_kem_key, data.as_slice())
+            {
+                return Ok(CryptData::from(sealed));
+            }
+        }
+        Ok(CryptData::from(
+            cipher_suite::suite_for(key).seal(&remote_public_key, data.as_slice())?,
+        ))
     }
 
     fn decode(&self, data: &CryptData) -> Result<PlainData, CryptdecError> {
         if data.len() < SEALBYTES {
             return Err(CryptdecError::EmptyData);
         }
-        match open(
-            data.as_slice(),
-            &Self::encryption_public_key_from(self.public_key()),
-            &self.encryption_secret_key,
-        ) {
-            Ok(data) => Ok(PlainData::from(data)),
-            Err(()) => Err(CryptdecError::OpeningFailed),
+        let encryption_public_key = Self::encryption_public_key_from(self.public_key());
+        let hybrid_opt = self.kem_key_pair_opt.as_ref().and_then(|kem_key_pair| {
+            hybrid_kem::open(
+                &encryption_public_key,
+                &self.encryption_secret_key,
+                kem_key_pair,
+                data.as_slice(),
+            )
+        });
+        match hybrid_opt.or_else(|| {
+            cipher_suite::open_with_any_suite(
+                &encryption_public_key,
+                &self.encryption_secret_key,
+                data.as_slice(),
+            )
+        }) {
+            Some(data) => Ok(PlainData::from(data)),
+            None => match &self.previous_opt {
+                Some(previous) if !previous.is_retired() => previous.cryptde.decode(data),
+                _ => Err(CryptdecError::OpeningFailed),
+            },
         }
     }
 
@@ -92,7 +136,11 @@ impl CryptDE for CryptDEReal {
     }
 
     fn random(&self, dest: &mut [u8]) {
-        randombytes_into(dest);
+        if deterministic::is_deterministic() {
+            deterministic::fill_random("CryptDE", dest)
+        } else {
+            randombytes_into(dest)
+        }
     }
 
     fn private_key(&self) -> &PrivateKey {
@@ -105,13 +153,7 @@ impl CryptDE for CryptDEReal {
     }
 
     fn dup(&self) -> Box<dyn CryptDE> {
-        Box::new(CryptDEReal {
-            public_key: self.public_key.clone(),
-            encryption_secret_key: encryption::SecretKey(self.encryption_secret_key.0),
-            signing_secret_key: signing::SecretKey(self.signing_secret_key.0),
-            digest: self.digest,
-            pre_shared_data: self.pre_shared_data,
-        })
+        Box::new(self.dup_real())
     }
 
     fn sign(&self, data: &PlainData) -> Result<CryptData, CryptdecError> {
@@ -179,10 +221,40 @@ impl CryptDE for CryptDEReal {
     fn digest(&self) -> [u8; 32] {
         self.digest
     }
+
+    fn features(&self) -> BTreeSet<String> {
+        let mut features = cipher_suite::local_features();
+        if self.kem_key_pair_opt.is_some() {
+            features.insert(hybrid_kem::X25519_KYBER768_CHACHA20_POLY1305.to_string());
+        }
+        features
+    }
+
+    fn kem_public_key_opt(&self) -> Option<PlainData> {
+        self.kem_key_pair_opt
+            .as_ref()
+            .map(|kem_key_pair| PlainData::from(kem_key_pair.public_key_bytes()))
+    }
+
+    fn key_succession_opt(&self) -> Option<KeySuccession> {
+        match &self.previous_opt {
+            Some(previous) if !previous.is_retired() => Some(KeySuccession {
+                previous_public_key: previous.cryptde.public_key().clone(),
+                endorsement: previous.endorsement.clone(),
+            }),
+            _ => None,
+        }
+    }
 }
 
 impl CryptDEReal {
     pub fn new(chain_id: u8) -> Self {
+        if let Some(seed) = deterministic::deterministic_bytes_opt(
+            "clandestine key",
+            cxsp::SECRETKEYBYTES + signing::SEEDBYTES,
+        ) {
+            return Self::from_seed(&seed, chain_id);
+        }
         let (e_public, e_secret) = encryption::gen_keypair();
         let (s_public, s_secret) = signing::gen_keypair();
         let public_key = Self::local_public_key_from(&e_public, &s_public);
@@ -195,6 +267,103 @@ impl CryptDEReal {
             signing_secret_key: s_secret,
             digest,
             pre_shared_data,
+            previous_opt: None,
+            kem_key_pair_opt: None,
+        }
+    }
+
+    fn from_seed(seed: &[u8], chain_id: u8) -> Self {
+        let signing_seed = signing::Seed::from_slice(&seed[cxsp::SECRETKEYBYTES..])
+            .expect("Signing seed length changed");
+        let (_, s_secret) = signing::keypair_from_seed(&signing_seed);
+        let secret_key_bytes = [&seed[..cxsp::SECRETKEYBYTES], &s_secret.0[..]].concat();
+        Self::from_secret_key_bytes(&secret_key_bytes, chain_id)
+            .expect("Secret key lengths changed")
+    }
+
+    // The inverse of secret_key_bytes()
+    pub fn from_secret_key_bytes(bytes: &[u8], chain_id: u8) -> Result<Self, String> {
+        if bytes.len() != cxsp::SECRETKEYBYTES + signing::SECRETKEYBYTES {
+            return Err(format!(
+                "Secret key must be {} bytes, not {}",
+                cxsp::SECRETKEYBYTES + signing::SECRETKEYBYTES,
+                bytes.len()
+            ));
+        }
+        let e_secret = encryption::SecretKey::from_slice(&bytes[..cxsp::SECRETKEYBYTES])
+            .expect("Encryption secret key length changed");
+        let s_secret = signing::SecretKey::from_slice(&bytes[cxsp::SECRETKEYBYTES..])
+            .expect("Signing secret key length changed");
+        let public_key =
+            Self::local_public_key_from(&e_secret.public_key(), &s_secret.public_key());
+        let digest = cryptde::create_digest(&public_key, &contract_address(chain_id));
+        Ok(Self {
+            public_key,
+            encryption_secret_key: e_secret,
+            signing_secret_key: s_secret,
+            digest,
+            pre_shared_data: contract_address(chain_id).0,
+            previous_opt: None,
+            kem_key_pair_opt: None,
+        })
+    }
+
+    // Everything needed to recreate this CryptDE's key later; keep it secret
+    pub fn secret_key_bytes(&self) -> Vec<u8> {
+        [
+            &self.encryption_secret_key.0[..],
+            &self.signing_secret_key.0[..],
+        ]
+        .concat()
+    }
+
+    // Until retire_at, this CryptDE will decode data encrypted for previous too, and announce
+    // that it succeeds previous
+    pub fn succeed(mut self, previous: CryptDEReal, retire_at: SystemTime) -> Self {
+        let endorsement = previous
+            .sign(&PlainData::new(self.public_key.as_slice()))
+            .expect("Signing failed");
+        self.previous_opt = Some(PreviousKey {
+            cryptde: Box::new(previous),
+            retire_at,
+            endorsement,
+        });
+        self
+    }
+
+    // Agrees to hybrid post-quantum sealing with any Node that has agreed to it too
+    pub fn with_kem_key_pair(mut self, kem_key_pair: KemKeyPair) -> Self {
+        self.kem_key_pair_opt = Some(kem_key_pair);
+        self
+    }
+
+    pub fn kem_key_pair_opt(&self) -> Option<&KemKeyPair> {
+        self.kem_key_pair_opt.as_ref()
+    }
+
+    // Hybrid sealing is used only when both ends have agreed to it
+    fn hybrid_kem_key_for(&self, key: &PublicKey) -> Option<Vec<u8>> {
+        if self.kem_key_pair_opt.is_none()
+            || !cipher_suite::peer_has_feature(key, hybrid_kem::X25519_KYBER768_CHACHA20_POLY1305)
+        {
+            return None;
+        }
+        hybrid_kem::peer_kem_key_opt(key)
+    }
+
+    fn dup_real(&self) -> CryptDEReal {
+        CryptDEReal {
+            public_key: self.public_key.clone(),
+            encryption_secret_key: encryption::SecretKey(self.encryption_secret_key.0),
+            signing_secret_key: signing::SecretKey(self.signing_secret_key.0),
+            digest: self.digest,
+            pre_shared_data: self.pre_shared_data,
+            previous_opt: self.previous_opt.as_ref().map(|previous| PreviousKey {
+                cryptde: Box::new(previous.cryptde.dup_real()),
+                retire_at: previous.retire_at,
+                endorsement: previous.endorsement.clone(),
+            }),
+            kem_key_pair_opt: self.kem_key_pair_opt.clone(),
         }
     }
 
@@ -244,6 +413,31 @@ mod tests {
         assert_ne!(first_subject.public_key(), second_subject.public_key());
     }
 
+    #[test]
+    fn construction_with_a_deterministic_seed_generates_the_same_keys_every_time() {
+        deterministic::set_deterministic_seed(Some(1234));
+        let first_subject = CryptDEReal::default();
+        deterministic::set_deterministic_seed(Some(1234));
+        let second_subject = CryptDEReal::default();
+        deterministic::set_deterministic_seed(Some(4321));
+        let other_subject = CryptDEReal::default();
+        deterministic::set_deterministic_seed(None);
+
+        assert_eq!(first_subject.public_key(), second_subject.public_key());
+        assert_eq!(
+            first_subject.secret_key_bytes(),
+            second_subject.secret_key_bytes()
+        );
+        assert_ne!(first_subject.public_key(), other_subject.public_key());
+        let encrypted = first_subject
+            .encode(first_subject.public_key(), &PlainData::new(b"booga"))
+            .unwrap();
+        assert_eq!(
+            second_subject.decode(&encrypted).unwrap(),
+            PlainData::new(b"booga")
+        );
+    }
+
     #[test]
     fn dup_produces_identical_keys() {
         let subject = CryptDEReal::default();
@@ -359,6 +553,95 @@ mod tests {
         assert_eq!(decoded, data);
     }
 
+    #[test]
+    fn encode_uses_the_best_cipher_suite_the_recipient_announces() {
+        let legacy_recipient = CryptDEReal::default();
+        let modern_recipient = CryptDEReal::default();
+        cipher_suite::record_peer_features(
+            modern_recipient.public_key(),
+            &cipher_suite::local_features(),
+        );
+        let data = PlainData::new(b"Let me out!");
+
+        let legacy_encoded = legacy_recipient
+            .encode(legacy_recipient.public_key(), &data)
+            .unwrap();
+        let modern_encoded = modern_recipient
+            .encode(modern_recipient.public_key(), &data)
+            .unwrap();
+
+        assert_eq!(legacy_encoded.len(), data.len() + SEALBYTES);
+        assert_eq!(modern_encoded.len(), data.len() + 32 + 12 + 16);
+        assert_eq!(legacy_recipient.decode(&legacy_encoded).unwrap(), data);
+        assert_eq!(modern_recipient.decode(&modern_encoded).unwrap(), data);
+    }
+
+    #[test]
+    fn encode_refuses_a_low_order_key_instead_of_panicking() {
+        let subject = CryptDEReal::default();
+        let low_order_key = PublicKey::new(&[0u8; 64]);
+        cipher_suite::record_peer_features(&low_order_key, &cipher_suite::local_features());
+
+        let result = subject.encode(&low_order_key, &PlainData::new(b"data"));
+
+        assert_eq!(
+            result,
+            Err(CryptdecError::InvalidKey(format!("{:?}", [0u8; 32])))
+        );
+    }
+
+    #[test]
+    fn encode_uses_hybrid_sealing_only_when_both_ends_agree() {
+        let consenting_recipient = CryptDEReal::default().with_kem_key_pair(KemKeyPair::new());
+        let refusing_sender = CryptDEReal::default();
+        cipher_suite::record_peer_features(
+            consenting_recipient.public_key(),
+            &consenting_recipient.features(),
+        );
+        hybrid_kem::record_peer_kem_key(
+            consenting_recipient.public_key(),
+            consenting_recipient
+                .kem_public_key_opt()
+                .as_ref()
+                .map(|key| key.as_slice()),
+        );
+        let data = PlainData::new(b"Let me out!");
+
+        let hybrid_encoded = consenting_recipient
+            .encode(consenting_recipient.public_key(), &data)
+            .unwrap();
+        let classic_encoded = refusing_sender
+            .encode(consenting_recipient.public_key(), &data)
+            .unwrap();
+
+        assert_eq!(hybrid_encoded.len(), data.len() + 32 + 1088 + 12 + 16);
+        assert_eq!(classic_encoded.len(), data.len() + 32 + 12 + 16);
+        assert_eq!(consenting_recipient.decode(&hybrid_encoded).unwrap(), data);
+        assert_eq!(consenting_recipient.decode(&classic_encoded).unwrap(), data);
+    }
+
+    #[test]
+    fn kem_key_pair_advertises_hybrid_feature_and_survives_dup() {
+        let subject = CryptDEReal::default().with_kem_key_pair(KemKeyPair::new());
+
+        let dup = subject.dup();
+
+        assert!(subject
+            .features()
+            .contains(hybrid_kem::X25519_KYBER768_CHACHA20_POLY1305));
+        assert_eq!(
+            subject.kem_public_key_opt(),
+            Some(PlainData::from(
+                subject.kem_key_pair_opt().unwrap().public_key_bytes()
+            ))
+        );
+        assert_eq!(dup.kem_public_key_opt(), subject.kem_public_key_opt());
+        assert!(!CryptDEReal::default()
+            .features()
+            .contains(hybrid_kem::X25519_KYBER768_CHACHA20_POLY1305));
+        assert_eq!(CryptDEReal::default().kem_public_key_opt(), None);
+    }
+
     #[test]
     fn encode_sym_with_invalid_key() {
         let subject = CryptDEReal::default();
@@ -598,4 +881,71 @@ mod tests {
 
         assert_eq!(expected_digest, actual_digest);
     }
+
+    #[test]
+    fn secret_key_bytes_recreate_the_same_key() {
+        let original = CryptDEReal::default();
+
+        let result =
+            CryptDEReal::from_secret_key_bytes(&original.secret_key_bytes(), DEFAULT_CHAIN_ID)
+                .unwrap();
+
+        assert_eq!(result.public_key(), original.public_key());
+        assert_eq!(result.digest(), original.digest());
+        let data = PlainData::new(b"secret");
+        let encoded = original.encode(original.public_key(), &data).unwrap();
+        assert_eq!(result.decode(&encoded).unwrap(), data);
+    }
+
+    #[test]
+    fn secret_key_bytes_of_the_wrong_length_are_rejected() {
+        let result = CryptDEReal::from_secret_key_bytes(&[1, 2, 3], DEFAULT_CHAIN_ID);
+
+        assert_eq!(
+            result.err(),
+            Some("Secret key must be 96 bytes, not 3".to_string())
+        );
+    }
+
+    #[test]
+    fn successor_decodes_for_its_previous_key_until_it_is_retired() {
+        let previous = CryptDEReal::default();
+        let previous_public_key = previous.public_key().clone();
+        let data = PlainData::new(b"in flight");
+        let for_previous = previous.encode(&previous_public_key, &data).unwrap();
+        let subject = CryptDEReal::default().succeed(
+            previous.dup_real(),
+            SystemTime::now() + std::time::Duration::from_secs(3600),
+        );
+        let retired = CryptDEReal::default().succeed(previous, SystemTime::now());
+
+        assert_eq!(subject.decode(&for_previous).unwrap(), data);
+        assert_eq!(
+            retired.decode(&for_previous),
+            Err(CryptdecError::OpeningFailed)
+        );
+        let for_subject = subject.encode(subject.public_key(), &data).unwrap();
+        assert_eq!(subject.decode(&for_subject).unwrap(), data);
+        assert_eq!(retired.key_succession_opt(), None);
+    }
+
+    #[test]
+    fn successor_announces_an_endorsed_key_succession() {
+        let previous = CryptDEReal::default();
+        let previous_public_key = previous.public_key().clone();
+        let subject = CryptDEReal::default().succeed(
+            previous,
+            SystemTime::now() + std::time::Duration::from_secs(3600),
+        );
+
+        let succession = subject.key_succession_opt().unwrap();
+
+        assert_eq!(succession.previous_public_key, previous_public_key);
+        assert_eq!(succession.is_endorsed(&subject, subject.public_key()), true);
+        assert_eq!(
+            succession.is_endorsed(&subject, CryptDEReal::default().public_key()),
+            false
+        );
+        assert_eq!(subject.dup().key_succession_opt(), Some(succession));
+    }
 }
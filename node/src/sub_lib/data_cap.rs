@@ -0,0 +1,242 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use chrono::{DateTime, Local, Timelike};
+use masq_lib::data_cap::DataCapRate;
+#[cfg(test)]
+use std::cell::Cell;
+#[cfg(not(test))]
+use std::sync::atomic::{AtomicU8, Ordering};
+
+pub const BYTES_PER_GB: u64 = 1_000_000_000;
+// Once this much of the month's budget is gone, the Node stops taking on new exit streams
+pub const THROTTLE_PERCENT: u64 = 90;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DataCapStatus {
+    // Serving everybody
+    Open,
+    // Finishing the exit streams it has, but opening no new ones
+    Throttled,
+    // Neither routing nor exiting for other Nodes until the month is over
+    Exhausted,
+}
+
+impl DataCapStatus {
+    pub fn name(self) -> &'static str {
+        match self {
+            DataCapStatus::Open => "open",
+            DataCapStatus::Throttled => "throttled",
+            DataCapStatus::Exhausted => "exhausted",
+        }
+    }
+
+    #[cfg(not(test))]
+    fn from_u8(value: u8) -> DataCapStatus {
+        match value {
+            0 => DataCapStatus::Open,
+            1 => DataCapStatus::Throttled,
+            _ => DataCapStatus::Exhausted,
+        }
+    }
+
+    #[cfg(not(test))]
+    fn to_u8(self) -> u8 {
+        match self {
+            DataCapStatus::Open => 0,
+            DataCapStatus::Throttled => 1,
+            DataCapStatus::Exhausted => 2,
+        }
+    }
+}
+
+// The Accountant keeps this up to date; the Hopper and the ProxyClient consult it before serving
+#[cfg(not(test))]
+static STATUS: AtomicU8 = AtomicU8::new(0);
+
+// Tests run in parallel on many threads, and most of them expect a Node that serves everybody
+#[cfg(test)]
+thread_local! {
+    static STATUS: Cell<DataCapStatus> = Cell::new(DataCapStatus::Open);
+}
+
+#[cfg(not(test))]
+pub fn set_status(status: DataCapStatus) {
+    STATUS.store(status.to_u8(), Ordering::Relaxed);
+}
+
+#[cfg(not(test))]
+pub fn status() -> DataCapStatus {
+    DataCapStatus::from_u8(STATUS.load(Ordering::Relaxed))
+}
+
+#[cfg(test)]
+pub fn set_status(status: DataCapStatus) {
+    STATUS.with(|cell| cell.set(status));
+}
+
+#[cfg(test)]
+pub fn status() -> DataCapStatus {
+    STATUS.with(|cell| cell.get())
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DataCapConfig {
+    pub monthly_bytes: u64,
+    pub rates: Vec<DataCapRate>,
+}
+
+impl DataCapConfig {
+    // The first rate that covers the hour wins; hours no rate covers count in full
+    pub fn percent_at(&self, hour: u32) -> u64 {
+        self.rates
+            .iter()
+            .find(|rate| rate.covers(hour))
+            .map(|rate| rate.percent)
+            .unwrap_or(100)
+    }
+}
+
+// How much of this month's budget routing and exit service for other Nodes has used
+pub struct DataCapTracker {
+    config: DataCapConfig,
+    month: String,
+    used_bytes: u64,
+}
+
+impl DataCapTracker {
+    pub fn new(config: DataCapConfig, now: DateTime<Local>, used_bytes: u64) -> DataCapTracker {
+        DataCapTracker {
+            config,
+            month: Self::month_of(now),
+            used_bytes,
+        }
+    }
+
+    pub fn month_of(now: DateTime<Local>) -> String {
+        now.format("%Y-%m").to_string()
+    }
+
+    pub fn record(&mut self, bytes: u64, now: DateTime<Local>) -> DataCapStatus {
+        self.roll_over(now);
+        let percent = self.config.percent_at(now.hour());
+        self.used_bytes = self
+            .used_bytes
+            .saturating_add(bytes.saturating_mul(percent) / 100);
+        self.status()
+    }
+
+    // A new month brings a new budget
+    pub fn roll_over(&mut self, now: DateTime<Local>) -> DataCapStatus {
+        let month = Self::month_of(now);
+        if month != self.month {
+            self.month = month;
+            self.used_bytes = 0;
+        }
+        self.status()
+    }
+
+    pub fn status(&self) -> DataCapStatus {
+        if self.used_bytes >= self.config.monthly_bytes {
+            DataCapStatus::Exhausted
+        } else if self.used_bytes.saturating_mul(100)
+            >= self.config.monthly_bytes.saturating_mul(THROTTLE_PERCENT)
+        {
+            DataCapStatus::Throttled
+        } else {
+            DataCapStatus::Open
+        }
+    }
+
+    pub fn monthly_bytes(&self) -> u64 {
+        self.config.monthly_bytes
+    }
+
+    pub fn used_bytes(&self) -> u64 {
+        self.used_bytes
+    }
+
+    pub fn remaining_bytes(&self) -> u64 {
+        self.config.monthly_bytes.saturating_sub(self.used_bytes)
+    }
+
+    pub fn month(&self) -> &str {
+        &self.month
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::str::FromStr;
+
+    fn make_config(monthly_bytes: u64, rates: &[&str]) -> DataCapConfig {
+        DataCapConfig {
+            monthly_bytes,
+            rates: rates
+                .iter()
+                .map(|rate| DataCapRate::from_str(rate).unwrap())
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn the_first_rate_covering_an_hour_decides_how_much_it_counts() {
+        let subject = make_config(1000, &["22-6:0", "0-12:50"]);
+
+        assert_eq!(subject.percent_at(23), 0);
+        assert_eq!(subject.percent_at(5), 0);
+        assert_eq!(subject.percent_at(6), 50);
+        assert_eq!(subject.percent_at(11), 50);
+        assert_eq!(subject.percent_at(12), 100);
+    }
+
+    #[test]
+    fn tracker_throttles_near_the_cap_and_stops_at_it() {
+        let now = Local.ymd(2020, 11, 2).and_hms(12, 0, 0);
+        let mut subject = DataCapTracker::new(make_config(1000, &[]), now, 0);
+
+        assert_eq!(subject.record(899, now), DataCapStatus::Open);
+        assert_eq!(subject.record(1, now), DataCapStatus::Throttled);
+        assert_eq!(subject.remaining_bytes(), 100);
+        assert_eq!(subject.record(100, now), DataCapStatus::Exhausted);
+        assert_eq!(subject.remaining_bytes(), 0);
+    }
+
+    #[test]
+    fn tracker_weighs_usage_by_time_of_day() {
+        let night = Local.ymd(2020, 11, 2).and_hms(3, 0, 0);
+        let evening = Local.ymd(2020, 11, 2).and_hms(20, 0, 0);
+        let mut subject = DataCapTracker::new(make_config(1000, &["0-6:0", "18-22:50"]), night, 0);
+
+        subject.record(500, night);
+        subject.record(500, evening);
+
+        assert_eq!(subject.used_bytes(), 250);
+    }
+
+    #[test]
+    fn tracker_starts_over_in_a_new_month() {
+        let november = Local.ymd(2020, 11, 30).and_hms(12, 0, 0);
+        let december = Local.ymd(2020, 12, 1).and_hms(12, 0, 0);
+        let mut subject = DataCapTracker::new(make_config(1000, &[]), november, 1000);
+        assert_eq!(subject.status(), DataCapStatus::Exhausted);
+
+        let result = subject.roll_over(december);
+
+        assert_eq!(result, DataCapStatus::Open);
+        assert_eq!(subject.used_bytes(), 0);
+        assert_eq!(subject.month(), "2020-12");
+    }
+
+    #[test]
+    fn status_starts_open_and_remembers_what_it_is_told() {
+        assert_eq!(status(), DataCapStatus::Open);
+
+        set_status(DataCapStatus::Throttled);
+        let throttled = status();
+        set_status(DataCapStatus::Open);
+
+        assert_eq!(throttled, DataCapStatus::Throttled);
+        assert_eq!(status(), DataCapStatus::Open);
+    }
+}
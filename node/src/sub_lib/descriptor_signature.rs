@@ -0,0 +1,211 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use crate::sub_lib::wallet::{Wallet, WalletError};
+use ethsign::Signature;
+use ethsign_crypto::Keccak256;
+use rustc_hex::{FromHex, ToHex};
+use std::str::FromStr;
+
+// A signed descriptor is <descriptor>#<earning wallet>#<signature>; neither descriptors nor
+// wallet addresses ever contain a '#'
+pub const SIGNATURE_DELIMITER: char = '#';
+
+const SIGNATURE_LEN: usize = 65;
+
+// The descriptor is signed as an Ethereum signed message, so that an operator can sign it with
+// whatever holds the earning wallet's key, using its "sign message" feature
+pub fn descriptor_digest(descriptor: &str) -> [u8; 32] {
+    format!(
+        "\x19Ethereum Signed Message:\n{}{}",
+        descriptor.len(),
+        descriptor
+    )
+    .as_bytes()
+    .keccak256()
+}
+
+pub fn sign_descriptor(descriptor: &str, earning_wallet: &Wallet) -> Result<String, WalletError> {
+    Ok(signed_descriptor(
+        descriptor,
+        earning_wallet,
+        &sign_message(descriptor, earning_wallet)?,
+    ))
+}
+
+// Any other message a wallet's owner has to vouch for is signed the same way
+pub fn sign_message(message: &str, wallet: &Wallet) -> Result<String, WalletError> {
+    let signature = wallet.sign(&descriptor_digest(message))?;
+    Ok(signature_to_string(&signature))
+}
+
+pub fn signed_descriptor(descriptor: &str, earning_wallet: &Wallet, signature: &str) -> String {
+    format!(
+        "{}{}{}{}{}",
+        descriptor, SIGNATURE_DELIMITER, earning_wallet, SIGNATURE_DELIMITER, signature
+    )
+}
+
+pub fn is_signed_by(descriptor: &str, earning_wallet: &Wallet, signature: &str) -> bool {
+    let signature = match signature_from_str(signature) {
+        Ok(signature) => signature,
+        Err(_) => return false,
+    };
+    match signature.recover(&descriptor_digest(descriptor)) {
+        Ok(public_key) => public_key.address() == &earning_wallet.address().0,
+        Err(_) => false,
+    }
+}
+
+// Splits a descriptor that may be signed into the plain descriptor and the earning wallet that
+// signed it. A signature that isn't the named wallet's is an error: whoever published the
+// descriptor doesn't own that wallet, and may be impersonating the Node that does.
+pub fn verify_signed_descriptor(s: &str) -> Result<(String, Option<Wallet>), String> {
+    let pieces: Vec<&str> = s.split(SIGNATURE_DELIMITER).collect();
+    match pieces.as_slice() {
+        [descriptor] => Ok((descriptor.to_string(), None)),
+        [descriptor, wallet, signature] => {
+            let earning_wallet = match Wallet::from_str(wallet) {
+                Ok(earning_wallet) => earning_wallet,
+                Err(_) => {
+                    return Err(format!(
+                        "'{}' in signed descriptor '{}' isn't an earning wallet address",
+                        wallet, s
+                    ))
+                }
+            };
+            if let Err(e) = signature_from_str(signature) {
+                return Err(format!("{} in signed descriptor '{}'", e, s));
+            }
+            if is_signed_by(descriptor, &earning_wallet, signature) {
+                Ok((descriptor.to_string(), Some(earning_wallet)))
+            } else {
+                Err(format!(
+                    "Descriptor {} wasn't signed by earning wallet {}; it may be an impersonation",
+                    descriptor, earning_wallet
+                ))
+            }
+        }
+        _ => Err(format!(
+            "Should be <descriptor>[#<earning wallet>#<signature>], not '{}'",
+            s
+        )),
+    }
+}
+
+fn signature_to_string(signature: &Signature) -> String {
+    let mut bytes = Vec::with_capacity(SIGNATURE_LEN);
+    bytes.extend_from_slice(&signature.r);
+    bytes.extend_from_slice(&signature.s);
+    bytes.push(signature.v + 27);
+    format!("0x{}", bytes.to_hex::<String>())
+}
+
+// Ethereum wallets put 27 or 28 in the last byte where ethsign expects 0 or 1; either will do
+fn signature_from_str(signature: &str) -> Result<Signature, String> {
+    let bytes: Vec<u8> = match signature.trim_start_matches("0x").from_hex::<Vec<u8>>() {
+        Ok(bytes) if bytes.len() == SIGNATURE_LEN => bytes,
+        _ => {
+            return Err(format!(
+                "Signature should be 0x followed by {} hexadecimal digits, not '{}'",
+                SIGNATURE_LEN * 2,
+                signature
+            ))
+        }
+    };
+    let mut r = [0u8; 32];
+    r.copy_from_slice(&bytes[0..32]);
+    let mut s = [0u8; 32];
+    s.copy_from_slice(&bytes[32..64]);
+    let v = match bytes[64] {
+        v if v >= 27 => v - 27,
+        v => v,
+    };
+    Ok(Signature { v, r, s })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{make_paying_wallet, make_wallet};
+
+    const DESCRIPTOR: &str = "AQIDBA@1.2.3.4:1234;2345";
+
+    #[test]
+    fn a_descriptor_signed_by_its_earning_wallet_verifies() {
+        let earning_wallet = make_paying_wallet(b"descriptor operator");
+        let signed = sign_descriptor(DESCRIPTOR, &earning_wallet).unwrap();
+
+        let result = verify_signed_descriptor(&signed);
+
+        assert_eq!(
+            result,
+            Ok((
+                DESCRIPTOR.to_string(),
+                Some(earning_wallet.as_address_wallet())
+            ))
+        );
+        assert_eq!(
+            verify_signed_descriptor(DESCRIPTOR),
+            Ok((DESCRIPTOR.to_string(), None))
+        );
+    }
+
+    #[test]
+    fn a_signature_moved_to_another_descriptor_or_wallet_is_refused() {
+        let earning_wallet = make_paying_wallet(b"descriptor operator");
+        let signed = sign_descriptor(DESCRIPTOR, &earning_wallet).unwrap();
+        let signature = signed.rsplit(SIGNATURE_DELIMITER).next().unwrap();
+        let impostor_wallet = make_wallet("impostor");
+        let other_descriptor = "AQIDBA@6.6.6.6:1234";
+
+        let wrong_wallet =
+            verify_signed_descriptor(&signed_descriptor(DESCRIPTOR, &impostor_wallet, signature));
+        let wrong_descriptor = verify_signed_descriptor(&signed_descriptor(
+            other_descriptor,
+            &earning_wallet,
+            signature,
+        ));
+
+        assert_eq!(
+            wrong_wallet,
+            Err(format!(
+                "Descriptor {} wasn't signed by earning wallet {}; it may be an impersonation",
+                DESCRIPTOR, impostor_wallet
+            ))
+        );
+        assert_eq!(
+            wrong_descriptor,
+            Err(format!(
+                "Descriptor {} wasn't signed by earning wallet {}; it may be an impersonation",
+                other_descriptor, earning_wallet
+            ))
+        );
+    }
+
+    #[test]
+    fn malformed_signed_descriptors_are_explained() {
+        let wallet = make_wallet("operator");
+
+        assert_eq!(
+            verify_signed_descriptor(&format!("{}#booga", DESCRIPTOR)),
+            Err(format!(
+                "Should be <descriptor>[#<earning wallet>#<signature>], not '{}#booga'",
+                DESCRIPTOR
+            ))
+        );
+        assert_eq!(
+            verify_signed_descriptor(&format!("{}#booga#0x00", DESCRIPTOR)),
+            Err(format!(
+                "'booga' in signed descriptor '{}#booga#0x00' isn't an earning wallet address",
+                DESCRIPTOR
+            ))
+        );
+        assert_eq!(
+            verify_signed_descriptor(&signed_descriptor(DESCRIPTOR, &wallet, "0x1234")),
+            Err(format!(
+                "Signature should be 0x followed by 130 hexadecimal digits, not '0x1234' in \
+                 signed descriptor '{}'",
+                signed_descriptor(DESCRIPTOR, &wallet, "0x1234")
+            ))
+        );
+    }
+}
@@ -0,0 +1,145 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+#[cfg(not(test))]
+use lazy_static::lazy_static;
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+use sodiumoxide::crypto::hash::sha256;
+#[cfg(test)]
+use std::cell::RefCell;
+use std::collections::HashMap;
+#[cfg(not(test))]
+use std::sync::Mutex;
+
+// Set by the hidden --deterministic-seed parameter. While it's set, everything that would otherwise
+// be random comes from the seed instead. Each purpose gets its own stream of numbers, so that one
+// actor taking numbers doesn't change what another one gets, however their threads are scheduled.
+struct DeterministicState {
+    seed: u64,
+    draws: HashMap<String, u64>,
+}
+
+impl DeterministicState {
+    fn fill(&mut self, purpose: &str, bytes: &mut [u8]) {
+        let draw = self.draws.entry(purpose.to_string()).or_insert(0);
+        let digest = sha256::hash(
+            &[
+                &self.seed.to_be_bytes()[..],
+                purpose.as_bytes(),
+                &draw.to_be_bytes()[..],
+            ]
+            .concat(),
+        );
+        *draw += 1;
+        StdRng::from_seed(digest.0).fill_bytes(bytes);
+    }
+}
+
+#[cfg(not(test))]
+lazy_static! {
+    static ref STATE: Mutex<Option<DeterministicState>> = Mutex::new(None);
+}
+
+// Tests run in parallel on many threads, so each thread has its own state, and it starts out random
+#[cfg(test)]
+thread_local! {
+    static STATE: RefCell<Option<DeterministicState>> = RefCell::new(None);
+}
+
+#[cfg(not(test))]
+fn with_state<T, F: FnOnce(&mut Option<DeterministicState>) -> T>(f: F) -> T {
+    f(&mut STATE.lock().expect("Deterministic state is poisoned"))
+}
+
+#[cfg(test)]
+fn with_state<T, F: FnOnce(&mut Option<DeterministicState>) -> T>(f: F) -> T {
+    STATE.with(|cell| f(&mut cell.borrow_mut()))
+}
+
+pub fn set_deterministic_seed(seed_opt: Option<u64>) {
+    with_state(|state_opt| {
+        *state_opt = seed_opt.map(|seed| DeterministicState {
+            seed,
+            draws: HashMap::new(),
+        })
+    })
+}
+
+pub fn is_deterministic() -> bool {
+    with_state(|state_opt| state_opt.is_some())
+}
+
+// For things that make their own randomness, like key pairs: None means go ahead and do that
+pub fn deterministic_bytes_opt(purpose: &str, len: usize) -> Option<Vec<u8>> {
+    with_state(|state_opt| {
+        state_opt.as_mut().map(|state| {
+            let mut bytes = vec![0u8; len];
+            state.fill(purpose, &mut bytes);
+            bytes
+        })
+    })
+}
+
+pub fn fill_random(purpose: &str, bytes: &mut [u8]) {
+    with_state(|state_opt| match state_opt {
+        Some(state) => state.fill(purpose, bytes),
+        None => rand::thread_rng().fill_bytes(bytes),
+    })
+}
+
+pub fn random_u64(purpose: &str) -> u64 {
+    let mut bytes = [0u8; 8];
+    fill_random(purpose, &mut bytes);
+    u64::from_be_bytes(bytes)
+}
+
+pub fn random_u16(purpose: &str) -> u16 {
+    let mut bytes = [0u8; 2];
+    fill_random(purpose, &mut bytes);
+    u16::from_be_bytes(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_same_seed_produces_the_same_numbers() {
+        set_deterministic_seed(Some(1234));
+        let first_run = (0..3).map(|_| random_u64("booga")).collect::<Vec<u64>>();
+        set_deterministic_seed(Some(1234));
+        let second_run = (0..3).map(|_| random_u64("booga")).collect::<Vec<u64>>();
+        set_deterministic_seed(Some(4321));
+        let other_seed_run = (0..3).map(|_| random_u64("booga")).collect::<Vec<u64>>();
+
+        set_deterministic_seed(None);
+        assert_eq!(first_run, second_run);
+        assert_ne!(first_run, other_seed_run);
+        assert_ne!(first_run[0], first_run[1]);
+    }
+
+    #[test]
+    fn each_purpose_has_its_own_stream() {
+        set_deterministic_seed(Some(1234));
+        let undisturbed = (random_u64("booga"), random_u64("booga"));
+        set_deterministic_seed(Some(1234));
+        let first = random_u64("booga");
+        let _ = random_u16("other");
+        let _ = deterministic_bytes_opt("another", 32);
+        let second = random_u64("booga");
+
+        set_deterministic_seed(None);
+        assert_eq!((first, second), undisturbed);
+    }
+
+    #[test]
+    fn without_a_seed_there_are_no_deterministic_bytes() {
+        set_deterministic_seed(None);
+
+        assert_eq!(is_deterministic(), false);
+        assert_eq!(deterministic_bytes_opt("booga", 32), None);
+        assert_ne!(
+            (0..4).map(|_| random_u64("booga")).collect::<Vec<u64>>(),
+            vec![random_u64("booga"); 4]
+        );
+    }
+}
@@ -0,0 +1,178 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use crate::sub_lib::sequence_buffer::SequencedPacket;
+use std::convert::TryFrom;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static NEXT_FILE_NUMBER: AtomicUsize = AtomicUsize::new(0);
+
+// Packets a stream has no room for in memory wait in a file of their own, in the order they came,
+// until they can go out. The file is created when the first packet arrives, emptied whenever
+// every packet in it has been read back, and deleted when the overflow is dropped.
+pub struct DiskOverflow {
+    path: PathBuf,
+    file_opt: Option<File>,
+    read_offset: u64,
+    write_offset: u64,
+    bytes: usize,
+}
+
+impl Drop for DiskOverflow {
+    fn drop(&mut self) {
+        if self.file_opt.take().is_some() {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+impl DiskOverflow {
+    pub fn new(directory: &Path) -> DiskOverflow {
+        let file_name = format!(
+            "masq-overflow-{}-{}",
+            std::process::id(),
+            NEXT_FILE_NUMBER.fetch_add(1, Ordering::Relaxed)
+        );
+        DiskOverflow {
+            path: directory.join(file_name),
+            file_opt: None,
+            read_offset: 0,
+            write_offset: 0,
+            bytes: 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.read_offset == self.write_offset
+    }
+
+    // Payload bytes waiting on disk
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    pub fn push(&mut self, packet: &SequencedPacket) -> io::Result<()> {
+        let data_len = u32::try_from(packet.data.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Packet too large"))?;
+        let mut record = Vec::with_capacity(13 + packet.data.len());
+        record.extend_from_slice(&packet.sequence_number.to_be_bytes());
+        record.push(packet.last_data as u8);
+        record.extend_from_slice(&data_len.to_be_bytes());
+        record.extend_from_slice(&packet.data);
+        let write_offset = self.write_offset;
+        let file = self.file()?;
+        file.seek(SeekFrom::Start(write_offset))?;
+        file.write_all(&record)?;
+        self.write_offset += record.len() as u64;
+        self.bytes += packet.data.len();
+        Ok(())
+    }
+
+    pub fn pop(&mut self) -> io::Result<Option<SequencedPacket>> {
+        if self.is_empty() {
+            return Ok(None);
+        }
+        let read_offset = self.read_offset;
+        let mut header = [0u8; 13];
+        let data = {
+            let file = self.file()?;
+            file.seek(SeekFrom::Start(read_offset))?;
+            file.read_exact(&mut header)?;
+            let mut data_len = [0u8; 4];
+            data_len.copy_from_slice(&header[9..13]);
+            let mut data = vec![0u8; u32::from_be_bytes(data_len) as usize];
+            file.read_exact(&mut data)?;
+            data
+        };
+        self.read_offset += (header.len() + data.len()) as u64;
+        self.bytes -= data.len();
+        if self.is_empty() {
+            self.file()?.set_len(0)?;
+            self.read_offset = 0;
+            self.write_offset = 0;
+        }
+        let mut sequence_number = [0u8; 8];
+        sequence_number.copy_from_slice(&header[0..8]);
+        Ok(Some(SequencedPacket::new(
+            data,
+            u64::from_be_bytes(sequence_number),
+            header[8] != 0,
+        )))
+    }
+
+    fn file(&mut self) -> io::Result<&mut File> {
+        if self.file_opt.is_none() {
+            let file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(&self.path)?;
+            self.file_opt = Some(file);
+        }
+        Ok(self.file_opt.as_mut().expect("Overflow file disappeared"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use masq_lib::test_utils::utils::ensure_node_home_directory_exists;
+
+    #[test]
+    fn packets_come_back_in_the_order_they_went_in() {
+        let directory =
+            ensure_node_home_directory_exists("disk_overflow", "packets_come_back_in_order");
+        let mut subject = DiskOverflow::new(&directory);
+
+        subject
+            .push(&SequencedPacket::new(b"first".to_vec(), 3, false))
+            .unwrap();
+        subject
+            .push(&SequencedPacket::new(vec![], 4, false))
+            .unwrap();
+        let bytes_after_pushes = subject.bytes();
+        let first = subject.pop().unwrap();
+        subject
+            .push(&SequencedPacket::new(b"last".to_vec(), 5, true))
+            .unwrap();
+        let second = subject.pop().unwrap();
+        let third = subject.pop().unwrap();
+        let fourth = subject.pop().unwrap();
+
+        assert_eq!(bytes_after_pushes, 5);
+        assert_eq!(
+            first,
+            Some(SequencedPacket::new(b"first".to_vec(), 3, false))
+        );
+        assert_eq!(second, Some(SequencedPacket::new(vec![], 4, false)));
+        assert_eq!(third, Some(SequencedPacket::new(b"last".to_vec(), 5, true)));
+        assert_eq!(fourth, None);
+        assert_eq!(subject.is_empty(), true);
+        assert_eq!(subject.bytes(), 0);
+        assert_eq!(std::fs::metadata(&subject.path).unwrap().len(), 0);
+    }
+
+    #[test]
+    fn file_is_created_only_when_needed_and_deleted_on_drop() {
+        let directory = ensure_node_home_directory_exists(
+            "disk_overflow",
+            "file_is_created_only_when_needed_and_deleted_on_drop",
+        );
+        let mut subject = DiskOverflow::new(&directory);
+        let path = subject.path.clone();
+        let existed_before_push = path.exists();
+
+        subject
+            .push(&SequencedPacket::new(b"booga".to_vec(), 0, false))
+            .unwrap();
+        let existed_after_push = path.exists();
+        drop(subject);
+
+        assert_eq!(existed_before_push, false);
+        assert_eq!(existed_after_push, true);
+        assert_eq!(path.exists(), false);
+    }
+}
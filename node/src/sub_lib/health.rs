@@ -0,0 +1,24 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// The Neighborhood keeps these up to date as its database changes; readiness checks read them
+static NEIGHBORHOOD_CONNECTED: AtomicBool = AtomicBool::new(false);
+static ROUTES_AVAILABLE: AtomicBool = AtomicBool::new(false);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NeighborhoodReadiness {
+    pub connected: bool,
+    pub routes_available: bool,
+}
+
+pub fn report_neighborhood_readiness(readiness: NeighborhoodReadiness) {
+    NEIGHBORHOOD_CONNECTED.store(readiness.connected, Ordering::Relaxed);
+    ROUTES_AVAILABLE.store(readiness.routes_available, Ordering::Relaxed);
+}
+
+pub fn neighborhood_readiness() -> NeighborhoodReadiness {
+    NeighborhoodReadiness {
+        connected: NEIGHBORHOOD_CONNECTED.load(Ordering::Relaxed),
+        routes_available: ROUTES_AVAILABLE.load(Ordering::Relaxed),
+    }
+}
@@ -0,0 +1,166 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use crate::sub_lib::logger::Logger;
+use crate::sub_lib::stream_connector::{ConnectionInfo, StreamConnector, StreamConnectorReal};
+use futures::future::{err, loop_fn, Either, Loop};
+use net2::TcpBuilder;
+use std::io;
+use std::io::ErrorKind;
+use std::net::TcpStream as StdTcpStream;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener};
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use tokio::prelude::Future;
+use tokio::reactor::Handle;
+use tokio::timer::{Delay, Timeout};
+
+// Each side keeps connecting until the other side's attempt has opened its NAT, which takes a
+// little while because the two sides hear about each other at slightly different times.
+pub const HOLE_PUNCH_ATTEMPTS: usize = 5;
+pub const HOLE_PUNCH_ATTEMPT_MS: u64 = 1000;
+// How long a Node that asked for an Introduction waits for the other side to answer
+pub const HOLE_PUNCH_TIMEOUT_SECS: u64 = 30;
+
+pub type PunchFuture = Box<dyn Future<Item = ConnectionInfo, Error = io::Error> + Send>;
+
+// A port nobody else is using just now, to punch from. NATs that preserve port numbers will show
+// it to the peer unchanged, which is what the peer is told to expect.
+pub fn pick_local_port() -> io::Result<u16> {
+    let listener = TcpListener::bind(SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0))?;
+    Ok(listener.local_addr()?.port())
+}
+
+// Connects to peer_addr from local_port over and over until a connection is made or the attempts
+// run out. When the peer is doing the same thing at the same time, the two attempts meet
+// somewhere in the middle, and each NAT lets the other's SYN in as a reply to its own.
+pub fn punch(local_port: u16, peer_addr: SocketAddr, logger: &Logger) -> PunchFuture {
+    let logger = logger.clone();
+    Box::new(
+        loop_fn(1, move |attempt| {
+            let deadline = Instant::now() + Duration::from_millis(HOLE_PUNCH_ATTEMPT_MS);
+            let logger = logger.clone();
+            connect_from(local_port, peer_addr).then(move |result| match result {
+                Ok(stream) => Either::A(futures::future::ok(Loop::Break((stream, logger)))),
+                Err(e) if attempt >= HOLE_PUNCH_ATTEMPTS => Either::A(err(e)),
+                Err(e) => {
+                    debug!(
+                        logger,
+                        "Hole-punching attempt {} from port {} to {} failed: {}",
+                        attempt,
+                        local_port,
+                        peer_addr,
+                        e
+                    );
+                    Either::B(
+                        Delay::new(deadline)
+                            .map(move |_| Loop::Continue(attempt + 1))
+                            .map_err(|e| io::Error::new(ErrorKind::Other, e)),
+                    )
+                }
+            })
+        })
+        .and_then(|(stream, logger)| {
+            StreamConnectorReal {}
+                .split_stream(stream, &logger)
+                .ok_or_else(|| io::Error::from(ErrorKind::NotConnected))
+        }),
+    )
+}
+
+fn connect_from(
+    local_port: u16,
+    peer_addr: SocketAddr,
+) -> Box<dyn Future<Item = TcpStream, Error = io::Error> + Send> {
+    let socket = match bound_socket(local_port, &peer_addr) {
+        Ok(socket) => socket,
+        Err(e) => return Box::new(err(e)),
+    };
+    Box::new(
+        Timeout::new(
+            TcpStream::connect_std(socket, &peer_addr, &Handle::default()),
+            Duration::from_millis(HOLE_PUNCH_ATTEMPT_MS),
+        )
+        .map_err(|wrapped_error| match wrapped_error.into_inner() {
+            Some(error) => error,
+            None => io::Error::from(ErrorKind::TimedOut),
+        }),
+    )
+}
+
+// Every attempt has to come from the same port, even while the last one is still winding down
+fn bound_socket(local_port: u16, peer_addr: &SocketAddr) -> io::Result<StdTcpStream> {
+    let (builder, unspecified) = match peer_addr {
+        SocketAddr::V4(_) => (TcpBuilder::new_v4()?, IpAddr::V4(Ipv4Addr::UNSPECIFIED)),
+        SocketAddr::V6(_) => (TcpBuilder::new_v6()?, IpAddr::V6(Ipv6Addr::UNSPECIFIED)),
+    };
+    builder.reuse_address(true)?;
+    builder.bind(SocketAddr::new(unspecified, local_port))?;
+    builder.to_tcp_stream()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use masq_lib::utils::{find_free_port, localhost};
+    use std::io::Read;
+    use std::thread;
+
+    #[test]
+    fn picked_port_is_free_to_punch_from() {
+        let port = pick_local_port().unwrap();
+
+        let result = bound_socket(port, &SocketAddr::new(localhost(), 1234));
+
+        assert_eq!(result.is_ok(), true);
+    }
+
+    #[test]
+    fn punch_connects_from_the_chosen_port() {
+        let listener = TcpListener::bind(SocketAddr::new(localhost(), 0)).unwrap();
+        let peer_addr = listener.local_addr().unwrap();
+        let local_port = pick_local_port().unwrap();
+        let (tx, rx) = std::sync::mpsc::channel();
+        thread::spawn(move || {
+            let (mut stream, remote_addr) = listener.accept().unwrap();
+            tx.send(remote_addr).unwrap();
+            let _ = stream.read(&mut [0u8; 1]);
+        });
+
+        let (tx_result, rx_result) = std::sync::mpsc::channel();
+        tokio::run(
+            punch(local_port, peer_addr, &Logger::new("test")).then(move |result| {
+                tx_result
+                    .send(result.map(|connection_info| {
+                        (connection_info.local_addr, connection_info.peer_addr)
+                    }))
+                    .unwrap();
+                Ok(())
+            }),
+        );
+
+        let (local_addr, connected_peer_addr) = rx_result.recv().unwrap().unwrap();
+        assert_eq!(local_addr.port(), local_port);
+        assert_eq!(connected_peer_addr, peer_addr);
+        assert_eq!(rx.recv().unwrap().port(), local_port);
+    }
+
+    #[test]
+    fn punch_gives_up_when_the_attempts_run_out() {
+        let peer_addr = SocketAddr::new(localhost(), find_free_port());
+        let local_port = pick_local_port().unwrap();
+        let started = Instant::now();
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        tokio::run(
+            punch(local_port, peer_addr, &Logger::new("test")).then(move |result| {
+                tx.send(result.err().map(|e| e.kind())).unwrap();
+                Ok(())
+            }),
+        );
+
+        assert_eq!(rx.recv().unwrap(), Some(ErrorKind::ConnectionRefused));
+        assert!(
+            started.elapsed()
+                >= Duration::from_millis(HOLE_PUNCH_ATTEMPT_MS * (HOLE_PUNCH_ATTEMPTS as u64 - 1))
+        );
+    }
+}
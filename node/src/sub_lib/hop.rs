@@ -16,6 +16,17 @@ pub struct LiveHop {
     pub public_key: PublicKey,
     pub payer: Option<Payer>,
     pub component: Component,
+    // When the originator wants to know how long its packages spend on each hop, the Node that
+    // reads this hop stamps the package with a HopTiming encrypted to this key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timing_key_opt: Option<PublicKey>,
+}
+
+// Which Node sent a package on, and when, by its own clock
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct HopTiming {
+    pub public_key: PublicKey,
+    pub sent_at_ms: u64,
 }
 
 impl LiveHop {
@@ -24,9 +35,15 @@ impl LiveHop {
             public_key: key.clone(),
             payer,
             component,
+            timing_key_opt: None,
         }
     }
 
+    pub fn with_timing_key(mut self, timing_key: &PublicKey) -> Self {
+        self.timing_key_opt = Some(timing_key.clone());
+        self
+    }
+
     pub fn decode(cryptde: &dyn CryptDE, crypt_data: &CryptData) -> Result<Self, CodexError> {
         decodex::<LiveHop>(cryptde, crypt_data)
     }
@@ -67,6 +84,21 @@ mod tests {
 
         assert_eq!(subject.public_key, key);
         assert_eq!(subject.component, Component::Neighborhood);
+        assert_eq!(subject.timing_key_opt, None);
+    }
+
+    #[test]
+    fn timing_key_survives_encoding_and_is_left_out_when_absent() {
+        let cryptde = main_cryptde();
+        let untimed = LiveHop::new(&PublicKey::new(&[1, 2]), None, Component::Hopper);
+        let timed = untimed.clone().with_timing_key(&PublicKey::new(&[3, 4]));
+
+        let untimed_encoded = untimed.encode(cryptde.public_key(), cryptde).unwrap();
+        let timed_encoded = timed.encode(cryptde.public_key(), cryptde).unwrap();
+
+        assert_eq!(LiveHop::decode(cryptde, &timed_encoded).unwrap(), timed);
+        assert_eq!(LiveHop::decode(cryptde, &untimed_encoded).unwrap(), untimed);
+        assert!(timed_encoded.len() > untimed_encoded.len());
     }
 
     #[test]
@@ -1,21 +1,32 @@
 // Copyright (c) 2017-2019, Substratum LLC (https://substratum.net) and/or its affiliates. All rights reserved.
 use crate::neighborhood::gossip::Gossip_0v1;
+use crate::sub_lib::accountant::PaymentOverdue_0v1;
 use crate::sub_lib::cryptde::encodex;
 use crate::sub_lib::cryptde::CryptDE;
 use crate::sub_lib::cryptde::CryptData;
 use crate::sub_lib::cryptde::PublicKey;
 use crate::sub_lib::dispatcher::InboundClientData;
-use crate::sub_lib::neighborhood::GossipFailure_0v1;
+use crate::sub_lib::neighborhood::{Benchmark_0v1, GossipFailure_0v1, HolePunch_0v1};
 use crate::sub_lib::node_addr::NodeAddr;
 use crate::sub_lib::peer_actors::BindMessage;
-use crate::sub_lib::proxy_client::{ClientResponsePayload_0v1, DnsResolveFailure_0v1};
-use crate::sub_lib::proxy_server::ClientRequestPayload_0v1;
+use crate::sub_lib::proxy_client::{
+    ClientResponsePayload_0v1, ClientUdpResponsePayload_0v1, DnsResolveFailure_0v1,
+};
+use crate::sub_lib::proxy_server::{
+    ClientRequestPayload_0v1, ClientUdpRequestPayload_0v1, StreamAckPayload_0v1,
+};
 use crate::sub_lib::route::Route;
-use crate::sub_lib::versioned_data::VersionedData;
+use crate::sub_lib::versioned_data::{DataVersion, VersionedData};
 use crate::sub_lib::wallet::Wallet;
 use actix::Message;
 use actix::Recipient;
+use serde::de::value::MapAccessDeserializer;
+use serde::de::{self, DeserializeSeed, IgnoredAny, IntoDeserializer, MapAccess, Visitor};
+use serde::forward_to_deserialize_any;
+use serde_cbor::value::from_value;
+use serde_cbor::Value;
 use serde_derive::{Deserialize, Serialize};
+use std::fmt;
 use std::fmt::Debug;
 use std::net::SocketAddr;
 
@@ -63,13 +74,237 @@ pub struct IncipientCoresPackage {
     pub payload: CryptData,
 }
 
+// The version of the envelope every CORES payload travels in. A Node refuses an envelope with a
+// newer major version, but skips fields it doesn't know in one with a newer minor version.
+pub const PAYLOAD_ENVELOPE_VERSION: DataVersion = DataVersion { major: 1, minor: 0 };
+
+// The derived serde functions are inherent (MessageType::serialize and MessageType::deserialize)
+// and produce the bare form, {"<variant name>": <payload>}. The Serialize and Deserialize traits
+// below put that in the envelope, {"version": <envelope version>, "body": <bare form>}, which keeps
+// payloads self-describing, and still accept the bare form from Nodes older than the envelope.
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(remote = "Self")]
 pub enum MessageType {
     ClientRequest(VersionedData<ClientRequestPayload_0v1>),
     ClientResponse(VersionedData<ClientResponsePayload_0v1>),
     Gossip(VersionedData<Gossip_0v1>),
     GossipFailure(VersionedData<GossipFailure_0v1>),
+    HolePunch(VersionedData<HolePunch_0v1>),
+    Benchmark(VersionedData<Benchmark_0v1>),
     DnsResolveFailed(VersionedData<DnsResolveFailure_0v1>),
+    ClientUdpRequest(VersionedData<ClientUdpRequestPayload_0v1>),
+    ClientUdpResponse(VersionedData<ClientUdpResponsePayload_0v1>),
+    StreamAck(VersionedData<StreamAckPayload_0v1>),
+    PaymentOverdue(VersionedData<PaymentOverdue_0v1>),
+}
+
+#[derive(Serialize)]
+struct PayloadEnvelope<'a> {
+    version: DataVersion,
+    body: BareMessageType<'a>,
+}
+
+struct BareMessageType<'a>(&'a MessageType);
+
+impl<'a> serde::Serialize for BareMessageType<'a> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        MessageType::serialize(self.0, serializer)
+    }
+}
+
+struct OwnedBareMessageType(MessageType);
+
+impl<'de> serde::Deserialize<'de> for OwnedBareMessageType {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        MessageType::deserialize(deserializer).map(OwnedBareMessageType)
+    }
+}
+
+impl serde::Serialize for MessageType {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        PayloadEnvelope {
+            version: PAYLOAD_ENVELOPE_VERSION,
+            body: BareMessageType(self),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for MessageType {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_map(MessageTypeVisitor)
+    }
+}
+
+struct MessageTypeVisitor;
+
+impl<'de> Visitor<'de> for MessageTypeVisitor {
+    type Value = MessageType;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("a MessageType, enveloped or bare")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<MessageType, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut key_opt = map.next_key::<String>()?;
+        match key_opt {
+            Some(ref key) if key == "version" => (),
+            // The envelope always starts with its version, so this is a bare MessageType
+            Some(variant) => {
+                return MessageType::deserialize(MapAccessDeserializer::new(BareEntry {
+                    variant_opt: Some(variant),
+                    map,
+                }))
+            }
+            None => return Err(de::Error::invalid_length(0, &self)),
+        }
+        let mut version_opt: Option<DataVersion> = None;
+        let mut body_opt: Option<MessageType> = None;
+        while let Some(key) = key_opt {
+            match key.as_str() {
+                "version" => version_opt = Some(map.next_value()?),
+                "body" => match version_opt {
+                    Some(version) if version.major > PAYLOAD_ENVELOPE_VERSION.major => {
+                        return Err(de::Error::custom(format!(
+                            "envelope version {} is newer than {}",
+                            version, PAYLOAD_ENVELOPE_VERSION
+                        )))
+                    }
+                    _ => body_opt = Some(map.next_value::<OwnedBareMessageType>()?.0),
+                },
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+            key_opt = map.next_key()?;
+        }
+        body_opt.ok_or_else(|| de::Error::missing_field("body"))
+    }
+}
+
+// Hands the one entry of a bare MessageType, whose key has already been read, to the derived
+// MessageType::deserialize
+struct BareEntry<A> {
+    variant_opt: Option<String>,
+    map: A,
+}
+
+impl<'de, A> MapAccess<'de> for BareEntry<A>
+where
+    A: MapAccess<'de>,
+{
+    type Error = A::Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, A::Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        match self.variant_opt.take() {
+            Some(variant) => seed
+                .deserialize(IntoDeserializer::<A::Error>::into_deserializer(variant))
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, A::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        self.map.next_value_seed(seed)
+    }
+}
+
+// The names MessageType's variants go by on the wire, straight from the derived
+// MessageType::deserialize, so that they can't fall out of step with the enum
+fn message_type_names() -> &'static [&'static str] {
+    let mut names: &'static [&'static str] = &[];
+    let _ = MessageType::deserialize(VariantNames { names: &mut names });
+    names
+}
+
+struct VariantNames<'a> {
+    names: &'a mut &'static [&'static str],
+}
+
+impl<'de, 'a> serde::Deserializer<'de> for VariantNames<'a> {
+    type Error = de::value::Error;
+
+    fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        Err(de::Error::custom("only an enum has variant names"))
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        variants: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        *self.names = variants;
+        Err(de::Error::custom("only looking for variant names"))
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+impl MessageType {
+    // Newer versions of a known MessageType are migrated by their VersionedData, but a MessageType
+    // added by a newer Node, or one in a newer major version of the envelope, can't be deserialized
+    // at all. This finds the name such a payload was serialized under, so that it can be told apart
+    // from a corrupt one; None if the name is one this Node knows, or if the payload isn't shaped
+    // like a MessageType.
+    pub fn unrecognized_name(serialized: &[u8]) -> Option<String> {
+        let (version_opt, bare) = match serde_cbor::de::from_slice::<Value>(serialized) {
+            Ok(Value::Map(mut map)) if map.contains_key(&Value::Text("version".to_string())) => (
+                map.remove(&Value::Text("version".to_string()))
+                    .and_then(|version| from_value::<DataVersion>(version).ok()),
+                map.remove(&Value::Text("body".to_string()))?,
+            ),
+            Ok(bare) => (None, bare),
+            Err(_) => return None,
+        };
+        let name = match bare {
+            Value::Map(ref map) if map.len() == 1 => map.keys().next().cloned(),
+            Value::Array(ref items) if items.len() == 2 => items.first().cloned(),
+            _ => None,
+        };
+        match (name, version_opt) {
+            (Some(Value::Text(name)), Some(version))
+                if version.major > PAYLOAD_ENVELOPE_VERSION.major =>
+            {
+                Some(format!("{} in envelope version {}", name, version))
+            }
+            (Some(Value::Text(name)), _) if !message_type_names().contains(&name.as_str()) => {
+                Some(name)
+            }
+            _ => None,
+        }
+    }
 }
 
 impl IncipientCoresPackage {
@@ -290,4 +525,146 @@ mod tests {
         assert_eq!(subject.payload, payload);
         assert_eq!(subject.payload_len, 42);
     }
+
+    #[test]
+    fn message_types_travel_in_a_versioned_self_describing_envelope() {
+        let subject = make_meaningless_message_type();
+
+        let serialized = serde_cbor::ser::to_vec(&subject).unwrap();
+
+        let mut envelope = match serde_cbor::de::from_slice::<Value>(&serialized).unwrap() {
+            Value::Map(map) => map,
+            x => panic!("Expected a map; got {:?}", x),
+        };
+        assert_eq!(
+            from_value::<DataVersion>(
+                envelope
+                    .remove(&Value::Text("version".to_string()))
+                    .unwrap()
+            )
+            .unwrap(),
+            PAYLOAD_ENVELOPE_VERSION
+        );
+        match envelope.remove(&Value::Text("body".to_string())).unwrap() {
+            Value::Map(body) => assert_eq!(
+                body.keys().collect::<Vec<&Value>>(),
+                vec![&Value::Text("DnsResolveFailed".to_string())]
+            ),
+            x => panic!("Expected a map; got {:?}", x),
+        }
+        assert!(envelope.is_empty());
+        assert_eq!(
+            serde_cbor::de::from_slice::<MessageType>(&serialized).unwrap(),
+            subject
+        );
+    }
+
+    #[test]
+    fn bare_message_types_from_nodes_older_than_the_envelope_are_still_understood() {
+        let subject = make_meaningless_message_type();
+        let serialized = serde_cbor::ser::to_vec(&BareMessageType(&subject)).unwrap();
+
+        let result = serde_cbor::de::from_slice::<MessageType>(&serialized).unwrap();
+
+        assert_eq!(result, subject);
+    }
+
+    #[derive(Serialize)]
+    struct FutureEnvelope<'a> {
+        version: DataVersion,
+        body: BareMessageType<'a>,
+        priority: u8,
+    }
+
+    #[test]
+    fn fields_from_a_newer_minor_version_of_the_envelope_are_skipped() {
+        let subject = make_meaningless_message_type();
+        let serialized = serde_cbor::ser::to_vec(&FutureEnvelope {
+            version: DataVersion::new(1, 1),
+            body: BareMessageType(&subject),
+            priority: 7,
+        })
+        .unwrap();
+
+        let result = serde_cbor::de::from_slice::<MessageType>(&serialized).unwrap();
+
+        assert_eq!(result, subject);
+    }
+
+    #[test]
+    fn a_newer_major_version_of_the_envelope_is_refused_by_name() {
+        let subject = make_meaningless_message_type();
+        let serialized = serde_cbor::ser::to_vec(&FutureEnvelope {
+            version: DataVersion::new(2, 0),
+            body: BareMessageType(&subject),
+            priority: 7,
+        })
+        .unwrap();
+
+        let result = serde_cbor::de::from_slice::<MessageType>(&serialized);
+
+        assert_eq!(
+            result.err().unwrap().to_string(),
+            "envelope version 2.0 is newer than 1.0".to_string()
+        );
+        assert_eq!(
+            MessageType::unrecognized_name(&serialized),
+            Some("DnsResolveFailed in envelope version 2.0".to_string())
+        );
+    }
+
+    #[test]
+    fn message_type_names_are_the_enums_own() {
+        let result = message_type_names();
+
+        assert_eq!(
+            result,
+            &[
+                "ClientRequest",
+                "ClientResponse",
+                "Gossip",
+                "GossipFailure",
+                "HolePunch",
+                "Benchmark",
+                "DnsResolveFailed",
+                "ClientUdpRequest",
+                "ClientUdpResponse",
+                "StreamAck",
+                "PaymentOverdue",
+            ]
+        );
+    }
+
+    #[test]
+    fn message_types_from_newer_nodes_are_recognized_by_name() {
+        #[derive(Serialize)]
+        enum FutureMessageType {
+            Teleport(Vec<u8>),
+        }
+        #[derive(Serialize)]
+        struct Envelope {
+            version: DataVersion,
+            body: FutureMessageType,
+        }
+        let bare_future =
+            serde_cbor::ser::to_vec(&FutureMessageType::Teleport(vec![1, 2, 3])).unwrap();
+        let enveloped_future = serde_cbor::ser::to_vec(&Envelope {
+            version: PAYLOAD_ENVELOPE_VERSION,
+            body: FutureMessageType::Teleport(vec![1, 2, 3]),
+        })
+        .unwrap();
+        let known = serde_cbor::ser::to_vec(&make_meaningless_message_type()).unwrap();
+
+        assert_eq!(
+            MessageType::unrecognized_name(&bare_future),
+            Some("Teleport".to_string())
+        );
+        assert_eq!(
+            MessageType::unrecognized_name(&enveloped_future),
+            Some("Teleport".to_string())
+        );
+        assert!(serde_cbor::de::from_slice::<MessageType>(&enveloped_future).is_err());
+        assert_eq!(MessageType::unrecognized_name(&known), None);
+        assert_eq!(MessageType::unrecognized_name(&[0xFF, 0x00]), None);
+    }
 }
@@ -291,8 +291,7 @@ impl HttpPacketFramer {
                     .framer_state
                     .data_so_far
                     .split_off(chunk_offset_length.offset);
-                if (chunk_offset_length.length == 3)
-                    && (self.framer_state.data_so_far[chunk_offset_length.offset] == (b'0'))
+                if (chunk_offset_length.length == 3) && (self.framer_state.data_so_far[0] == (b'0'))
                 {
                     self.framer_state.chunk_progress_state =
                         ChunkProgressState::SeekingEndOfFinalChunk;
@@ -1052,6 +1051,30 @@ mod framer_tests {
         assert_eq!(subject.framer_state.chunk_size, None);
     }
 
+    #[test]
+    fn frames_final_chunk_that_follows_trash() {
+        let data = &b"trash0\r\n\r\n"[..];
+        let mut subject = HttpPacketFramer::new(Box::new(TameStartFinder {}));
+        subject.framer_state.transfer_encoding_chunked = ChunkExistenceState::Chunk;
+        subject.framer_state.chunk_progress_state = ChunkProgressState::SeekingLengthHeader;
+        subject.add_data(data);
+
+        let result = subject.take_frame();
+
+        assert_eq!(
+            result,
+            Some(FramedChunk {
+                chunk: Vec::from(&b"0\r\n\r\n"[..]),
+                last_chunk: false,
+            })
+        );
+        assert_eq!(subject.framer_state.data_so_far, Vec::from(&b""[..]));
+        assert_eq!(
+            subject.framer_state.chunk_progress_state,
+            ChunkProgressState::None
+        );
+    }
+
     #[test]
     fn frames_final_chunk_with_header() {
         let data1 = &b"13\r\nnineteen characters0\r\nHeader: "[..];
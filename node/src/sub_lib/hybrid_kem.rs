@@ -0,0 +1,269 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use crate::sub_lib::cryptde::PublicKey;
+use crate::sub_lib::peer_table::PeerTable;
+use lazy_static::lazy_static;
+use pqcrypto_kyber::kyber768;
+use pqcrypto_traits::kem::{Ciphertext, PublicKey as KemPublicKey, SecretKey, SharedSecret};
+use sodiumoxide::crypto::aead::chacha20poly1305_ietf as aead;
+use sodiumoxide::crypto::box_ as encryption;
+use sodiumoxide::crypto::hash::sha256;
+use sodiumoxide::crypto::scalarmult::curve25519;
+use sodiumoxide::randombytes::randombytes_into;
+use std::sync::RwLock;
+
+// Feature flag in a node record announcing that the Node will accept data sealed with both X25519
+// and Kyber768, so that recording its traffic now won't help someone with a quantum computer later.
+// It isn't one of the cipher suites, because it needs a Kyber key that isn't part of the Node's
+// public key: the Kyber key travels in the node record instead.
+pub const X25519_KYBER768_CHACHA20_POLY1305: &str = "cipher:x25519-kyber768-chacha20poly1305";
+
+lazy_static! {
+    static ref PEER_KEM_KEYS: RwLock<PeerTable<Vec<u8>>> = RwLock::new(PeerTable::default());
+}
+
+#[derive(Clone)]
+pub struct KemKeyPair {
+    public_key: kyber768::PublicKey,
+    secret_key: kyber768::SecretKey,
+}
+
+impl Default for KemKeyPair {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl KemKeyPair {
+    pub fn new() -> Self {
+        let (public_key, secret_key) = kyber768::keypair();
+        KemKeyPair {
+            public_key,
+            secret_key,
+        }
+    }
+
+    pub fn from_bytes(public_key: &[u8], secret_key: &[u8]) -> Result<Self, String> {
+        Ok(KemKeyPair {
+            public_key: kyber768::PublicKey::from_bytes(public_key)
+                .map_err(|e| format!("Bad Kyber public key: {:?}", e))?,
+            secret_key: kyber768::SecretKey::from_bytes(secret_key)
+                .map_err(|e| format!("Bad Kyber secret key: {:?}", e))?,
+        })
+    }
+
+    pub fn public_key_bytes(&self) -> &[u8] {
+        self.public_key.as_bytes()
+    }
+
+    // Keep it secret
+    pub fn secret_key_bytes(&self) -> &[u8] {
+        self.secret_key.as_bytes()
+    }
+}
+
+// Called whenever a node record we believe arrives; None means the Node hasn't consented
+pub fn record_peer_kem_key(public_key: &PublicKey, kem_public_key_opt: Option<&[u8]>) {
+    let mut peer_kem_keys = PEER_KEM_KEYS
+        .write()
+        .expect("Peer KEM key table is poisoned");
+    match kem_public_key_opt {
+        Some(kem_public_key) => {
+            peer_kem_keys.insert(public_key, kem_public_key.to_vec());
+        }
+        None => {
+            peer_kem_keys.remove(public_key);
+        }
+    }
+}
+
+// Called when the Node's record leaves the database
+pub fn forget_peer_kem_key(public_key: &PublicKey) {
+    PEER_KEM_KEYS
+        .write()
+        .expect("Peer KEM key table is poisoned")
+        .remove(public_key);
+}
+
+pub fn peer_kem_key_opt(public_key: &PublicKey) -> Option<Vec<u8>> {
+    PEER_KEM_KEYS
+        .read()
+        .expect("Peer KEM key table is poisoned")
+        .get(public_key)
+        .cloned()
+}
+
+// Sealed data is an ephemeral X25519 public key, then a Kyber ciphertext, then a nonce, then the
+// ChaCha20-Poly1305 ciphertext under a key hashed from both shared secrets and everything public,
+// so that it stays secret as long as either X25519 or Kyber does.
+pub fn seal(
+    recipient: &encryption::PublicKey,
+    recipient_kem_key: &[u8],
+    data: &[u8],
+) -> Option<Vec<u8>> {
+    let recipient_kem_key = kyber768::PublicKey::from_bytes(recipient_kem_key).ok()?;
+    let mut ephemeral_secret = curve25519::Scalar([0u8; curve25519::SCALARBYTES]);
+    randombytes_into(&mut ephemeral_secret.0);
+    let ephemeral_public = curve25519::scalarmult_base(&ephemeral_secret);
+    let classic_shared =
+        curve25519::scalarmult(&ephemeral_secret, &curve25519::GroupElement(recipient.0)).ok()?;
+    let (kem_shared, kem_ciphertext) = kyber768::encapsulate(&recipient_kem_key);
+    let key = derive_key(
+        &classic_shared.0,
+        kem_shared.as_bytes(),
+        &ephemeral_public.0,
+        kem_ciphertext.as_bytes(),
+        &recipient.0,
+    );
+    let mut nonce = aead::Nonce([0u8; aead::NONCEBYTES]);
+    randombytes_into(&mut nonce.0);
+    let mut result = ephemeral_public.0.to_vec();
+    result.extend_from_slice(kem_ciphertext.as_bytes());
+    result.extend_from_slice(&nonce.0);
+    result.extend(aead::seal(data, Some(&result), &nonce, &key));
+    Some(result)
+}
+
+pub fn open(
+    public_key: &encryption::PublicKey,
+    secret_key: &encryption::SecretKey,
+    kem_key_pair: &KemKeyPair,
+    data: &[u8],
+) -> Option<Vec<u8>> {
+    let kem_start = curve25519::GROUPELEMENTBYTES;
+    let nonce_start = kem_start + kyber768::ciphertext_bytes();
+    let ciphertext_start = nonce_start + aead::NONCEBYTES;
+    if data.len() < ciphertext_start + aead::TAGBYTES {
+        return None;
+    }
+    let mut ephemeral_public = curve25519::GroupElement([0u8; curve25519::GROUPELEMENTBYTES]);
+    ephemeral_public.0.copy_from_slice(&data[..kem_start]);
+    let kem_ciphertext = kyber768::Ciphertext::from_bytes(&data[kem_start..nonce_start]).ok()?;
+    let nonce = aead::Nonce::from_slice(&data[nonce_start..ciphertext_start])?;
+    let classic_shared =
+        curve25519::scalarmult(&curve25519::Scalar(secret_key.0), &ephemeral_public).ok()?;
+    let kem_shared = kyber768::decapsulate(&kem_ciphertext, &kem_key_pair.secret_key);
+    let key = derive_key(
+        &classic_shared.0,
+        kem_shared.as_bytes(),
+        &ephemeral_public.0,
+        kem_ciphertext.as_bytes(),
+        &public_key.0,
+    );
+    aead::open(
+        &data[ciphertext_start..],
+        Some(&data[..ciphertext_start]),
+        &nonce,
+        &key,
+    )
+    .ok()
+}
+
+fn derive_key(
+    classic_shared: &[u8],
+    kem_shared: &[u8],
+    ephemeral_public: &[u8],
+    kem_ciphertext: &[u8],
+    recipient: &[u8],
+) -> aead::Key {
+    let digest = sha256::hash(
+        &[
+            classic_shared,
+            kem_shared,
+            ephemeral_public,
+            kem_ciphertext,
+            recipient,
+        ]
+        .concat(),
+    );
+    aead::Key(digest.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_recipient() -> (encryption::PublicKey, encryption::SecretKey, KemKeyPair) {
+        let _ = sodiumoxide::init();
+        let (public_key, secret_key) = encryption::gen_keypair();
+        (public_key, secret_key, KemKeyPair::new())
+    }
+
+    #[test]
+    fn hybrid_opens_what_it_seals_and_nothing_else() {
+        let (public_key, secret_key, kem_key_pair) = make_recipient();
+        let (other_public_key, other_secret_key, other_kem_key_pair) = make_recipient();
+        let data = b"Harvest now, decrypt never";
+
+        let sealed = seal(&public_key, kem_key_pair.public_key_bytes(), data).unwrap();
+
+        assert_eq!(
+            open(&public_key, &secret_key, &kem_key_pair, &sealed),
+            Some(data.to_vec())
+        );
+        assert_eq!(
+            open(&other_public_key, &other_secret_key, &kem_key_pair, &sealed),
+            None
+        );
+        assert_eq!(
+            open(&public_key, &secret_key, &other_kem_key_pair, &sealed),
+            None
+        );
+    }
+
+    #[test]
+    fn tampered_hybrid_data_does_not_open() {
+        let (public_key, secret_key, kem_key_pair) = make_recipient();
+        let mut sealed = seal(&public_key, kem_key_pair.public_key_bytes(), b"booga").unwrap();
+        sealed[curve25519::GROUPELEMENTBYTES + 1] ^= 1;
+
+        let result = open(&public_key, &secret_key, &kem_key_pair, &sealed);
+
+        assert_eq!(result, None);
+        assert_eq!(
+            open(&public_key, &secret_key, &kem_key_pair, &[0u8; 100]),
+            None
+        );
+    }
+
+    #[test]
+    fn seal_refuses_a_malformed_kem_key() {
+        let (public_key, _, _) = make_recipient();
+
+        let result = seal(&public_key, b"not a Kyber key", b"booga");
+
+        assert_eq!(result, None);
+    }
+
+    #[test]
+    fn kem_key_pair_survives_a_round_trip_through_bytes() {
+        let subject = KemKeyPair::new();
+
+        let result =
+            KemKeyPair::from_bytes(subject.public_key_bytes(), subject.secret_key_bytes()).unwrap();
+
+        assert_eq!(result.public_key_bytes(), subject.public_key_bytes());
+        assert_eq!(result.secret_key_bytes(), subject.secret_key_bytes());
+        assert!(KemKeyPair::from_bytes(b"short", subject.secret_key_bytes()).is_err());
+    }
+
+    #[test]
+    fn peer_kem_keys_are_recorded_and_forgotten() {
+        let node_key = PublicKey::new(b"peer_kem_keys_are_recorded_and_forgotten");
+        let kem_key_pair = KemKeyPair::new();
+
+        assert_eq!(peer_kem_key_opt(&node_key), None);
+
+        record_peer_kem_key(&node_key, Some(kem_key_pair.public_key_bytes()));
+        assert_eq!(
+            peer_kem_key_opt(&node_key),
+            Some(kem_key_pair.public_key_bytes().to_vec())
+        );
+
+        record_peer_kem_key(&node_key, None);
+        assert_eq!(peer_kem_key_opt(&node_key), None);
+
+        record_peer_kem_key(&node_key, Some(kem_key_pair.public_key_bytes()));
+        forget_peer_kem_key(&node_key);
+        assert_eq!(peer_kem_key_opt(&node_key), None);
+    }
+}
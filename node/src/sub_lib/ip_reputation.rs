@@ -0,0 +1,147 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use actix::Message;
+use std::net::{IpAddr, Ipv4Addr, ToSocketAddrs};
+use std::time::Duration;
+
+// Lists don't change their minds often, and asking them too often can get a Node refused
+pub const REPUTATION_CHECK_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+// Below this, the sites consumers visit through the Node are likely to refuse or challenge them
+pub const REPUTATION_WARNING_THRESHOLD: u8 = 100;
+
+// What the Node learned about its own IP address from the lists it asked
+#[derive(Clone, Debug, PartialEq, Message)]
+pub struct ReputationReport {
+    pub ip_addr: IpAddr,
+    // The percentage of the lists that don't list the address
+    pub score: u8,
+    pub listed_by: Vec<String>,
+}
+
+pub trait ReputationChecker: Send + Sync {
+    fn is_listed(&self, ip_addr: Ipv4Addr, list: &str) -> bool;
+}
+
+// A DNS blocklist answers for an address it lists, with an address in 127.0.0.0/8, and has no
+// answer for one it doesn't. A list that can't be reached can't be told apart from one that has
+// no answer, so it counts as not listing the address.
+pub struct ReputationCheckerReal {}
+
+impl ReputationChecker for ReputationCheckerReal {
+    fn is_listed(&self, ip_addr: Ipv4Addr, list: &str) -> bool {
+        match (query_name(ip_addr, list).as_str(), 0).to_socket_addrs() {
+            Ok(mut answers) => answers.any(|answer| match answer.ip() {
+                IpAddr::V4(answer) => answer.is_loopback(),
+                IpAddr::V6(_) => false,
+            }),
+            Err(_) => false,
+        }
+    }
+}
+
+// 1.2.3.4 is looked up in zen.example.org as 4.3.2.1.zen.example.org
+pub fn query_name(ip_addr: Ipv4Addr, list: &str) -> String {
+    let octets = ip_addr.octets();
+    format!(
+        "{}.{}.{}.{}.{}",
+        octets[3],
+        octets[2],
+        octets[1],
+        octets[0],
+        list.trim_end_matches('.')
+    )
+}
+
+// Blocklists for IPv6 addresses are few and far between, so those aren't checked
+pub fn check_reputation(
+    checker: &dyn ReputationChecker,
+    ip_addr: IpAddr,
+    lists: &[String],
+) -> Option<ReputationReport> {
+    let ipv4_addr = match ip_addr {
+        IpAddr::V4(ipv4_addr) => ipv4_addr,
+        IpAddr::V6(_) => return None,
+    };
+    if lists.is_empty() {
+        return None;
+    }
+    let listed_by = lists
+        .iter()
+        .filter(|list| checker.is_listed(ipv4_addr, list))
+        .cloned()
+        .collect::<Vec<String>>();
+    let score = ((lists.len() - listed_by.len()) * 100 / lists.len()) as u8;
+    Some(ReputationReport {
+        ip_addr,
+        score,
+        listed_by,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    struct ReputationCheckerMock {
+        listing: Vec<&'static str>,
+    }
+
+    impl ReputationChecker for ReputationCheckerMock {
+        fn is_listed(&self, _ip_addr: Ipv4Addr, list: &str) -> bool {
+            self.listing.contains(&list)
+        }
+    }
+
+    fn lists(lists: &[&str]) -> Vec<String> {
+        lists.iter().map(|list| list.to_string()).collect()
+    }
+
+    #[test]
+    fn query_name_reverses_the_octets_in_front_of_the_list() {
+        assert_eq!(
+            query_name(Ipv4Addr::new(1, 2, 3, 4), "zen.example.org."),
+            "4.3.2.1.zen.example.org".to_string()
+        );
+    }
+
+    #[test]
+    fn check_reputation_scores_the_share_of_lists_that_dont_list_the_address() {
+        let checker = ReputationCheckerMock {
+            listing: vec!["bad.example.org"],
+        };
+        let ip_addr = IpAddr::from_str("1.2.3.4").unwrap();
+
+        let result = check_reputation(
+            &checker,
+            ip_addr,
+            &lists(&["good.example.org", "bad.example.org", "fine.example.org"]),
+        );
+
+        assert_eq!(
+            result,
+            Some(ReputationReport {
+                ip_addr,
+                score: 66,
+                listed_by: lists(&["bad.example.org"]),
+            })
+        );
+    }
+
+    #[test]
+    fn check_reputation_has_nothing_to_say_about_ipv6_or_without_lists() {
+        let checker = ReputationCheckerMock { listing: vec![] };
+
+        assert_eq!(
+            check_reputation(
+                &checker,
+                IpAddr::from_str("::1").unwrap(),
+                &lists(&["good.example.org"])
+            ),
+            None
+        );
+        assert_eq!(
+            check_reputation(&checker, IpAddr::from_str("1.2.3.4").unwrap(), &[]),
+            None
+        );
+    }
+}
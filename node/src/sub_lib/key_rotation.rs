@@ -0,0 +1,429 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use crate::db_config::persistent_configuration::{PersistentConfigError, PersistentConfiguration};
+use crate::sub_lib::cryptde::PlainData;
+use crate::sub_lib::cryptde_real::CryptDEReal;
+use crate::sub_lib::hybrid_kem::KemKeyPair;
+use serde_derive::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub const DEFAULT_KEY_OVERLAP_HOURS: u64 = 24;
+
+// The clandestine key is replaced once it has been in use for longer than lifetime; the key it
+// replaces keeps working for overlap afterward, so that Nodes with stale Gossip can still reach us.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyRotationConfig {
+    pub lifetime: Duration,
+    pub overlap: Duration,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct StoredActiveKey {
+    secret_key_bytes: Vec<u8>,
+    created_at: u64,
+    // The Kyber key lives and dies with the clandestine key, so that the node record we advertise
+    // doesn't change from run to run
+    #[serde(default)]
+    kem_key_pair_opt: Option<StoredKemKeyPair>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct StoredKemKeyPair {
+    public_key_bytes: Vec<u8>,
+    secret_key_bytes: Vec<u8>,
+}
+
+impl From<&KemKeyPair> for StoredKemKeyPair {
+    fn from(kem_key_pair: &KemKeyPair) -> Self {
+        StoredKemKeyPair {
+            public_key_bytes: kem_key_pair.public_key_bytes().to_vec(),
+            secret_key_bytes: kem_key_pair.secret_key_bytes().to_vec(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct StoredPreviousKey {
+    secret_key_bytes: Vec<u8>,
+    retire_at: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct StoredClandestineKeys {
+    active: StoredActiveKey,
+    previous_opt: Option<StoredPreviousKey>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum KeyRotationOutcome {
+    Created,
+    Rotated,
+    Retained,
+}
+
+// Produces the main CryptDE for this run: the stored key if it's still young enough, or a new key
+// endorsed by the stored one if not. Whatever is chosen is stored again for the next run. With
+// post_quantum, the CryptDE gets the Kyber key that goes with its clandestine key.
+pub fn load_or_rotate(
+    persistent_config: &mut dyn PersistentConfiguration,
+    db_password: &str,
+    config: &KeyRotationConfig,
+    post_quantum: bool,
+    chain_id: u8,
+    now: SystemTime,
+) -> Result<(CryptDEReal, KeyRotationOutcome), PersistentConfigError> {
+    let stored_opt = persistent_config
+        .clandestine_keys(db_password)?
+        .map(|bytes| {
+            serde_cbor::de::from_slice::<StoredClandestineKeys>(bytes.as_slice()).expect(
+                "Can't continue; clandestine key configuration is corrupt and cannot be deserialized.",
+            )
+        });
+    let now_secs = to_secs(now);
+    let (cryptde, mut stored, outcome) = match stored_opt {
+        None => {
+            let cryptde = CryptDEReal::new(chain_id);
+            let stored = StoredClandestineKeys {
+                active: StoredActiveKey {
+                    secret_key_bytes: cryptde.secret_key_bytes(),
+                    created_at: now_secs,
+                    kem_key_pair_opt: None,
+                },
+                previous_opt: None,
+            };
+            (cryptde, stored, KeyRotationOutcome::Created)
+        }
+        Some(stored) if now_secs >= stored.active.created_at + config.lifetime.as_secs() => {
+            let previous = from_stored_bytes(&stored.active.secret_key_bytes, chain_id);
+            let retire_at = now + config.overlap;
+            let cryptde = CryptDEReal::new(chain_id).succeed(previous, retire_at);
+            let stored = StoredClandestineKeys {
+                active: StoredActiveKey {
+                    secret_key_bytes: cryptde.secret_key_bytes(),
+                    created_at: now_secs,
+                    kem_key_pair_opt: None,
+                },
+                previous_opt: Some(StoredPreviousKey {
+                    secret_key_bytes: stored.active.secret_key_bytes,
+                    retire_at: to_secs(retire_at),
+                }),
+            };
+            (cryptde, stored, KeyRotationOutcome::Rotated)
+        }
+        Some(stored) => {
+            let active = from_stored_bytes(&stored.active.secret_key_bytes, chain_id);
+            let previous_opt = stored
+                .previous_opt
+                .filter(|previous| previous.retire_at > now_secs);
+            let cryptde = match &previous_opt {
+                Some(previous) => active.succeed(
+                    from_stored_bytes(&previous.secret_key_bytes, chain_id),
+                    UNIX_EPOCH + Duration::from_secs(previous.retire_at),
+                ),
+                None => active,
+            };
+            let stored = StoredClandestineKeys {
+                active: stored.active,
+                previous_opt,
+            };
+            (cryptde, stored, KeyRotationOutcome::Retained)
+        }
+    };
+    let kem_key_pair_opt = if post_quantum {
+        Some(match &stored.active.kem_key_pair_opt {
+            Some(stored_kem) => {
+                KemKeyPair::from_bytes(&stored_kem.public_key_bytes, &stored_kem.secret_key_bytes)
+                    .unwrap_or_else(|e| {
+                        panic!("Can't continue; stored Kyber key is unusable: {}", e)
+                    })
+            }
+            None => KemKeyPair::new(),
+        })
+    } else {
+        None
+    };
+    stored.active.kem_key_pair_opt = kem_key_pair_opt.as_ref().map(StoredKemKeyPair::from);
+    let cryptde = match kem_key_pair_opt {
+        Some(kem_key_pair) => cryptde.with_kem_key_pair(kem_key_pair),
+        None => cryptde,
+    };
+    persistent_config.set_clandestine_keys(
+        Some(PlainData::new(
+            &serde_cbor::ser::to_vec(&stored).expect("Serialization failed"),
+        )),
+        db_password,
+    )?;
+    Ok((cryptde, outcome))
+}
+
+fn from_stored_bytes(bytes: &[u8], chain_id: u8) -> CryptDEReal {
+    CryptDEReal::from_secret_key_bytes(bytes, chain_id)
+        .unwrap_or_else(|e| panic!("Can't continue; stored clandestine key is unusable: {}", e))
+}
+
+fn to_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .expect("System clock is before the epoch")
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sub_lib::cryptde::CryptDE;
+    use crate::test_utils::persistent_configuration_mock::PersistentConfigurationMock;
+    use masq_lib::test_utils::utils::DEFAULT_CHAIN_ID;
+    use std::sync::{Arc, Mutex};
+
+    fn config() -> KeyRotationConfig {
+        KeyRotationConfig {
+            lifetime: Duration::from_secs(30 * 86400),
+            overlap: Duration::from_secs(DEFAULT_KEY_OVERLAP_HOURS * 3600),
+        }
+    }
+
+    fn stored_bytes(stored: &StoredClandestineKeys) -> PlainData {
+        PlainData::new(&serde_cbor::ser::to_vec(stored).unwrap())
+    }
+
+    fn stored_from(params: &Arc<Mutex<Vec<(Option<PlainData>, String)>>>) -> StoredClandestineKeys {
+        let params = params.lock().unwrap();
+        assert_eq!(params.len(), 1);
+        assert_eq!(params[0].1, "password".to_string());
+        serde_cbor::de::from_slice(params[0].0.as_ref().unwrap().as_slice()).unwrap()
+    }
+
+    #[test]
+    fn creates_and_stores_a_key_when_there_is_none() {
+        let now = SystemTime::now();
+        let now_secs = to_secs(now);
+        let set_params_arc = Arc::new(Mutex::new(vec![]));
+        let mut persistent_config = PersistentConfigurationMock::new()
+            .clandestine_keys_result(Ok(None))
+            .set_clandestine_keys_params(&set_params_arc)
+            .set_clandestine_keys_result(Ok(()));
+
+        let (cryptde, outcome) = load_or_rotate(
+            &mut persistent_config,
+            "password",
+            &config(),
+            false,
+            DEFAULT_CHAIN_ID,
+            now,
+        )
+        .unwrap();
+
+        assert_eq!(outcome, KeyRotationOutcome::Created);
+        assert_eq!(cryptde.key_succession_opt(), None);
+        let stored = stored_from(&set_params_arc);
+        assert_eq!(stored.active.secret_key_bytes, cryptde.secret_key_bytes());
+        assert_eq!(stored.active.created_at, now_secs);
+        assert_eq!(stored.previous_opt, None);
+    }
+
+    #[test]
+    fn retains_a_young_key_along_with_its_unretired_predecessor() {
+        let now = SystemTime::now();
+        let now_secs = to_secs(now);
+        let active = CryptDEReal::new(DEFAULT_CHAIN_ID);
+        let previous = CryptDEReal::new(DEFAULT_CHAIN_ID);
+        let stored = StoredClandestineKeys {
+            active: StoredActiveKey {
+                secret_key_bytes: active.secret_key_bytes(),
+                created_at: now_secs - 3600,
+                kem_key_pair_opt: None,
+            },
+            previous_opt: Some(StoredPreviousKey {
+                secret_key_bytes: previous.secret_key_bytes(),
+                retire_at: now_secs + 3600,
+            }),
+        };
+        let set_params_arc = Arc::new(Mutex::new(vec![]));
+        let mut persistent_config = PersistentConfigurationMock::new()
+            .clandestine_keys_result(Ok(Some(stored_bytes(&stored))))
+            .set_clandestine_keys_params(&set_params_arc)
+            .set_clandestine_keys_result(Ok(()));
+
+        let (cryptde, outcome) = load_or_rotate(
+            &mut persistent_config,
+            "password",
+            &config(),
+            false,
+            DEFAULT_CHAIN_ID,
+            now,
+        )
+        .unwrap();
+
+        assert_eq!(outcome, KeyRotationOutcome::Retained);
+        assert_eq!(cryptde.public_key(), active.public_key());
+        let succession = cryptde.key_succession_opt().unwrap();
+        assert_eq!(&succession.previous_public_key, previous.public_key());
+        assert!(succession.is_endorsed(&cryptde, cryptde.public_key()));
+        assert_eq!(stored_from(&set_params_arc), stored);
+    }
+
+    #[test]
+    fn forgets_a_retired_predecessor() {
+        let now = SystemTime::now();
+        let now_secs = to_secs(now);
+        let active = CryptDEReal::new(DEFAULT_CHAIN_ID);
+        let stored = StoredClandestineKeys {
+            active: StoredActiveKey {
+                secret_key_bytes: active.secret_key_bytes(),
+                created_at: now_secs - 86400,
+                kem_key_pair_opt: None,
+            },
+            previous_opt: Some(StoredPreviousKey {
+                secret_key_bytes: CryptDEReal::new(DEFAULT_CHAIN_ID).secret_key_bytes(),
+                retire_at: now_secs,
+            }),
+        };
+        let set_params_arc = Arc::new(Mutex::new(vec![]));
+        let mut persistent_config = PersistentConfigurationMock::new()
+            .clandestine_keys_result(Ok(Some(stored_bytes(&stored))))
+            .set_clandestine_keys_params(&set_params_arc)
+            .set_clandestine_keys_result(Ok(()));
+
+        let (cryptde, outcome) = load_or_rotate(
+            &mut persistent_config,
+            "password",
+            &config(),
+            false,
+            DEFAULT_CHAIN_ID,
+            now,
+        )
+        .unwrap();
+
+        assert_eq!(outcome, KeyRotationOutcome::Retained);
+        assert_eq!(cryptde.public_key(), active.public_key());
+        assert_eq!(cryptde.key_succession_opt(), None);
+        assert_eq!(stored_from(&set_params_arc).previous_opt, None);
+    }
+
+    #[test]
+    fn rotates_an_old_key_and_keeps_it_for_the_overlap() {
+        let now = SystemTime::now();
+        let now_secs = to_secs(now);
+        let old = CryptDEReal::new(DEFAULT_CHAIN_ID);
+        let stored = StoredClandestineKeys {
+            active: StoredActiveKey {
+                secret_key_bytes: old.secret_key_bytes(),
+                created_at: now_secs - 30 * 86400,
+                kem_key_pair_opt: None,
+            },
+            previous_opt: None,
+        };
+        let set_params_arc = Arc::new(Mutex::new(vec![]));
+        let mut persistent_config = PersistentConfigurationMock::new()
+            .clandestine_keys_result(Ok(Some(stored_bytes(&stored))))
+            .set_clandestine_keys_params(&set_params_arc)
+            .set_clandestine_keys_result(Ok(()));
+
+        let (cryptde, outcome) = load_or_rotate(
+            &mut persistent_config,
+            "password",
+            &config(),
+            false,
+            DEFAULT_CHAIN_ID,
+            now,
+        )
+        .unwrap();
+
+        assert_eq!(outcome, KeyRotationOutcome::Rotated);
+        assert_ne!(cryptde.public_key(), old.public_key());
+        let succession = cryptde.key_succession_opt().unwrap();
+        assert_eq!(&succession.previous_public_key, old.public_key());
+        assert!(succession.is_endorsed(&cryptde, cryptde.public_key()));
+        let plain = PlainData::new(b"for the old key");
+        let encrypted = old.encode(old.public_key(), &plain).unwrap();
+        assert_eq!(cryptde.decode(&encrypted).unwrap(), plain);
+        let stored = stored_from(&set_params_arc);
+        assert_eq!(stored.active.secret_key_bytes, cryptde.secret_key_bytes());
+        assert_eq!(stored.active.created_at, now_secs);
+        assert_eq!(
+            stored.previous_opt,
+            Some(StoredPreviousKey {
+                secret_key_bytes: old.secret_key_bytes(),
+                retire_at: now_secs + DEFAULT_KEY_OVERLAP_HOURS * 3600,
+            })
+        );
+    }
+
+    #[test]
+    fn post_quantum_keeps_the_kyber_key_with_the_clandestine_key() {
+        let now = SystemTime::now();
+        let now_secs = to_secs(now);
+        let active = CryptDEReal::new(DEFAULT_CHAIN_ID);
+        let kem_key_pair = KemKeyPair::new();
+        let stored = StoredClandestineKeys {
+            active: StoredActiveKey {
+                secret_key_bytes: active.secret_key_bytes(),
+                created_at: now_secs - 3600,
+                kem_key_pair_opt: Some(StoredKemKeyPair::from(&kem_key_pair)),
+            },
+            previous_opt: None,
+        };
+        let set_params_arc = Arc::new(Mutex::new(vec![]));
+        let mut persistent_config = PersistentConfigurationMock::new()
+            .clandestine_keys_result(Ok(Some(stored_bytes(&stored))))
+            .set_clandestine_keys_params(&set_params_arc)
+            .set_clandestine_keys_result(Ok(()));
+
+        let (cryptde, _) = load_or_rotate(
+            &mut persistent_config,
+            "password",
+            &config(),
+            true,
+            DEFAULT_CHAIN_ID,
+            now,
+        )
+        .unwrap();
+
+        assert_eq!(
+            cryptde.kem_key_pair_opt().unwrap().public_key_bytes(),
+            kem_key_pair.public_key_bytes()
+        );
+        assert_eq!(stored_from(&set_params_arc), stored);
+    }
+
+    #[test]
+    fn post_quantum_makes_a_kyber_key_when_the_clandestine_key_has_none() {
+        let set_params_arc = Arc::new(Mutex::new(vec![]));
+        let mut persistent_config = PersistentConfigurationMock::new()
+            .clandestine_keys_result(Ok(None))
+            .set_clandestine_keys_params(&set_params_arc)
+            .set_clandestine_keys_result(Ok(()));
+
+        let (cryptde, _) = load_or_rotate(
+            &mut persistent_config,
+            "password",
+            &config(),
+            true,
+            DEFAULT_CHAIN_ID,
+            SystemTime::now(),
+        )
+        .unwrap();
+
+        let kem_key_pair = cryptde.kem_key_pair_opt().unwrap();
+        assert_eq!(
+            stored_from(&set_params_arc).active.kem_key_pair_opt,
+            Some(StoredKemKeyPair::from(kem_key_pair))
+        );
+    }
+
+    #[test]
+    fn password_errors_are_passed_along() {
+        let mut persistent_config = PersistentConfigurationMock::new()
+            .clandestine_keys_result(Err(PersistentConfigError::PasswordError));
+
+        let result = load_or_rotate(
+            &mut persistent_config,
+            "password",
+            &config(),
+            false,
+            DEFAULT_CHAIN_ID,
+            SystemTime::now(),
+        );
+
+        assert_eq!(result.err(), Some(PersistentConfigError::PasswordError));
+    }
+}
@@ -0,0 +1,142 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use serde_derive::{Deserialize, Serialize};
+use std::cmp::max;
+use std::time::Duration;
+
+// Announced among a Node's features when it measures its links and answers its neighbors' probes
+pub const LINK_BENCHMARK_FEATURE: &str = "link-benchmark";
+// Often enough to notice a link getting worse, seldom enough that the probes are no burden
+pub const BENCHMARK_INTERVAL: Duration = Duration::from_secs(5 * 60);
+// Big enough that the time it takes to cross a link says something about the link's bandwidth
+pub const BENCHMARK_PROBE_BYTES: usize = 64 * 1024;
+// A probe that hasn't been answered by the next round never will be
+pub const BENCHMARK_TIMEOUT: Duration = BENCHMARK_INTERVAL;
+// How far, in percent, each new measurement moves the figures a Node publishes
+pub const BENCHMARK_SMOOTHING_PERCENT: u64 = 25;
+// A speed test through the mesh reports whatever has come back by then
+pub const SPEED_TEST_TIMEOUT: Duration = Duration::from_secs(30);
+// What a link nobody has measured is assumed to cost a route, in milliseconds
+pub const UNMEASURED_LINK_COST_MS: u64 = 250;
+// The size of a typical CORES package, for weighing bandwidth against latency
+const TYPICAL_PACKAGE_BYTES: u64 = 16 * 1024;
+
+// How a clandestine link to one neighbor performs: the round trip of an empty probe, and the
+// rate at which a padded probe crossed it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LinkPerformance {
+    pub latency_ms: u32,
+    // Kilobytes per second, which is bytes per millisecond
+    pub throughput_kbps: u32,
+}
+
+impl LinkPerformance {
+    // The padded probe takes longer than the empty one by about as long as its padding takes
+    // to cross the link
+    pub fn from_round_trips(empty: Duration, padded: Duration, padding_bytes: usize) -> Self {
+        let transfer_ms = max(padded.checked_sub(empty).unwrap_or_default().as_millis(), 1);
+        LinkPerformance {
+            latency_ms: clamp(empty.as_millis()),
+            throughput_kbps: max(clamp(padding_bytes as u128 / transfer_ms), 1),
+        }
+    }
+
+    pub fn smoothed(&self, sample: &LinkPerformance) -> LinkPerformance {
+        LinkPerformance {
+            latency_ms: smooth(self.latency_ms, sample.latency_ms),
+            throughput_kbps: smooth(self.throughput_kbps, sample.throughput_kbps),
+        }
+    }
+
+    // About how many milliseconds a typical package takes to get across; lower is better
+    pub fn cost_ms(&self) -> u64 {
+        self.latency_ms as u64 + TYPICAL_PACKAGE_BYTES / max(self.throughput_kbps as u64, 1)
+    }
+}
+
+fn smooth(old: u32, new: u32) -> u32 {
+    ((old as u64 * (100 - BENCHMARK_SMOOTHING_PERCENT) + new as u64 * BENCHMARK_SMOOTHING_PERCENT)
+        / 100) as u32
+}
+
+fn clamp(value: u128) -> u32 {
+    if value > u32::MAX as u128 {
+        u32::MAX
+    } else {
+        value as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn performance_is_measured_from_the_difference_between_round_trips() {
+        let result = LinkPerformance::from_round_trips(
+            Duration::from_millis(40),
+            Duration::from_millis(104),
+            64 * 1024,
+        );
+
+        assert_eq!(
+            result,
+            LinkPerformance {
+                latency_ms: 40,
+                throughput_kbps: 1024,
+            }
+        );
+    }
+
+    #[test]
+    fn a_padded_probe_that_beats_the_empty_one_does_not_divide_by_zero() {
+        let result = LinkPerformance::from_round_trips(
+            Duration::from_millis(40),
+            Duration::from_millis(30),
+            64 * 1024,
+        );
+
+        assert_eq!(
+            result,
+            LinkPerformance {
+                latency_ms: 40,
+                throughput_kbps: 65536,
+            }
+        );
+    }
+
+    #[test]
+    fn smoothing_moves_a_quarter_of_the_way_toward_the_new_measurement() {
+        let subject = LinkPerformance {
+            latency_ms: 100,
+            throughput_kbps: 1000,
+        };
+
+        let result = subject.smoothed(&LinkPerformance {
+            latency_ms: 20,
+            throughput_kbps: 3000,
+        });
+
+        assert_eq!(
+            result,
+            LinkPerformance {
+                latency_ms: 80,
+                throughput_kbps: 1500,
+            }
+        );
+    }
+
+    #[test]
+    fn cost_weighs_bandwidth_against_latency() {
+        let fast_but_far = LinkPerformance {
+            latency_ms: 100,
+            throughput_kbps: 16384,
+        };
+        let near_but_slow = LinkPerformance {
+            latency_ms: 10,
+            throughput_kbps: 64,
+        };
+
+        assert_eq!(fast_but_far.cost_ms(), 101);
+        assert_eq!(near_but_slow.cost_ms(), 266);
+    }
+}
@@ -0,0 +1,248 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use crate::sub_lib::logger::Logger;
+use actix::{Message, Recipient};
+use net2::UdpBuilder;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+use std::time::{Duration, Instant};
+use trust_dns_resolver::proto::op::{Message as DnsMessage, MessageType, OpCode, Query};
+use trust_dns_resolver::proto::rr::rdata::TXT;
+use trust_dns_resolver::proto::rr::{Name, RData, Record, RecordType};
+
+pub const MDNS_GROUP: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 251);
+pub const MDNS_PORT: u16 = 5353;
+pub const MASQ_SERVICE_NAME: &str = "_masq._udp.local.";
+// Often enough that a Node started later is found soon, seldom enough to be no bother on the LAN
+pub const LOCAL_DISCOVERY_INTERVAL: Duration = Duration::from_secs(60);
+// Answering every query at once would let a chatty neighbor turn the Node into a flood
+pub const MINIMUM_ANNOUNCEMENT_SPACING: Duration = Duration::from_secs(1);
+const ANNOUNCEMENT_TTL: u32 = 120;
+const DESCRIPTOR_KEY: &str = "descriptor=";
+
+// Another Node on the local network told this one its descriptor. The descriptor names the
+// address the other Node is known by publicly; source is the one it can be reached at here.
+#[derive(Clone, Debug, PartialEq, Message)]
+pub struct LocalNodeDiscovered {
+    pub descriptor: String,
+    pub source: IpAddr,
+}
+
+pub fn make_query() -> Vec<u8> {
+    let mut query = DnsMessage::new();
+    query
+        .set_id(0)
+        .set_message_type(MessageType::Query)
+        .set_op_code(OpCode::Query)
+        .add_query(Query::query(service_name(), RecordType::PTR));
+    query.to_vec().expect("Couldn't serialize mDNS query")
+}
+
+// A DNS-SD answer pointing the MASQ service at this Node's instance, whose TXT record carries the
+// Node's descriptor
+pub fn make_announcement(instance: &str, descriptor: &str) -> Result<Vec<u8>, String> {
+    let instance_name = Name::from_ascii(instance)
+        .map(|label| label.append_domain(&service_name()))
+        .map_err(|e| format!("Bad instance name '{}': {}", instance, e))?;
+    let mut announcement = DnsMessage::new();
+    announcement
+        .set_id(0)
+        .set_message_type(MessageType::Response)
+        .set_op_code(OpCode::Query)
+        .set_authoritative(true)
+        .add_answer(Record::from_rdata(
+            service_name(),
+            ANNOUNCEMENT_TTL,
+            RData::PTR(instance_name.clone()),
+        ))
+        .add_answer(Record::from_rdata(
+            instance_name,
+            ANNOUNCEMENT_TTL,
+            RData::TXT(TXT::new(vec![format!("{}{}", DESCRIPTOR_KEY, descriptor)])),
+        ));
+    announcement
+        .to_vec()
+        .map_err(|e| format!("Couldn't serialize mDNS announcement: {}", e))
+}
+
+pub fn is_service_query(packet: &[u8]) -> bool {
+    match DnsMessage::from_vec(packet) {
+        Ok(message) => {
+            message.message_type() == MessageType::Query
+                && message
+                    .queries()
+                    .iter()
+                    .any(|query| query.name() == &service_name())
+        }
+        Err(_) => false,
+    }
+}
+
+// Other mDNS traffic on the LAN is none of the Node's business, and neither is anything in a
+// TXT record besides a MASQ descriptor
+pub fn descriptors_in(packet: &[u8]) -> Vec<String> {
+    let message = match DnsMessage::from_vec(packet) {
+        Ok(message) if message.message_type() == MessageType::Response => message,
+        _ => return vec![],
+    };
+    message
+        .answers()
+        .iter()
+        .chain(message.additionals().iter())
+        .filter(|record| service_name().zone_of(record.name()))
+        .flat_map(|record| match record.rdata() {
+            RData::TXT(txt) => txt
+                .txt_data()
+                .iter()
+                .filter_map(|data| std::str::from_utf8(data).ok())
+                .filter(|data| data.starts_with(DESCRIPTOR_KEY))
+                .map(|data| data[DESCRIPTOR_KEY.len()..].to_string())
+                .collect::<Vec<String>>(),
+            _ => vec![],
+        })
+        .collect()
+}
+
+// Announces the Node and asks after others every LOCAL_DISCOVERY_INTERVAL, answers other Nodes'
+// questions, and passes on every descriptor but its own. Only returns if the mDNS socket can't
+// be set up.
+pub fn run(
+    instance: &str,
+    descriptor: &str,
+    recipient: Recipient<LocalNodeDiscovered>,
+    logger: &Logger,
+) -> io::Result<()> {
+    let announcement = make_announcement(instance, descriptor)
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+    let query = make_query();
+    let socket = open_socket()?;
+    let group = SocketAddr::new(IpAddr::V4(MDNS_GROUP), MDNS_PORT);
+    let mut next_round = Instant::now();
+    let mut last_announcement_opt: Option<Instant> = None;
+    let mut buffer = [0u8; 9000];
+    loop {
+        let now = Instant::now();
+        if now >= next_round {
+            send(&socket, &announcement, group, logger);
+            send(&socket, &query, group, logger);
+            last_announcement_opt = Some(now);
+            next_round = now + LOCAL_DISCOVERY_INTERVAL;
+        }
+        let (length, source) = match socket.recv_from(&mut buffer) {
+            Ok(received) => received,
+            Err(ref e)
+                if e.kind() == io::ErrorKind::WouldBlock || e.kind() == io::ErrorKind::TimedOut =>
+            {
+                continue
+            }
+            Err(e) => {
+                debug!(logger, "Couldn't receive mDNS packet: {}", e);
+                continue;
+            }
+        };
+        let packet = &buffer[..length];
+        if is_service_query(packet) {
+            let spaced = match last_announcement_opt {
+                Some(last) => now >= last + MINIMUM_ANNOUNCEMENT_SPACING,
+                None => true,
+            };
+            if spaced {
+                send(&socket, &announcement, group, logger);
+                last_announcement_opt = Some(now);
+            }
+            continue;
+        }
+        descriptors_in(packet)
+            .into_iter()
+            .filter(|found| found != descriptor)
+            .for_each(|found| {
+                let _ = recipient.try_send(LocalNodeDiscovered {
+                    descriptor: found,
+                    source: source.ip(),
+                });
+            });
+    }
+}
+
+fn open_socket() -> io::Result<UdpSocket> {
+    let builder = UdpBuilder::new_v4()?;
+    builder.reuse_address(true)?;
+    #[cfg(unix)]
+    {
+        use net2::unix::UnixUdpBuilderExt;
+        builder.reuse_port(true)?;
+    }
+    let socket = builder.bind(SocketAddr::new(
+        IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        MDNS_PORT,
+    ))?;
+    socket.join_multicast_v4(&MDNS_GROUP, &Ipv4Addr::UNSPECIFIED)?;
+    socket.set_multicast_loop_v4(true)?;
+    socket.set_read_timeout(Some(MINIMUM_ANNOUNCEMENT_SPACING))?;
+    Ok(socket)
+}
+
+fn send(socket: &UdpSocket, packet: &[u8], group: SocketAddr, logger: &Logger) {
+    if let Err(e) = socket.send_to(packet, group) {
+        debug!(logger, "Couldn't send mDNS packet: {}", e);
+    }
+}
+
+fn service_name() -> Name {
+    Name::from_ascii(MASQ_SERVICE_NAME).expect("MASQ service name is malformed")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn an_announcement_carries_the_descriptor_it_was_made_with() {
+        let announcement = make_announcement("masq-0123abcd", "AQIDBA@1.2.3.4:1234/2345").unwrap();
+
+        let result = descriptors_in(&announcement);
+
+        assert_eq!(result, vec!["AQIDBA@1.2.3.4:1234/2345".to_string()]);
+        assert_eq!(is_service_query(&announcement), false);
+    }
+
+    #[test]
+    fn a_query_asks_for_the_masq_service_and_carries_no_descriptors() {
+        let query = make_query();
+
+        assert_eq!(is_service_query(&query), true);
+        assert_eq!(descriptors_in(&query), Vec::<String>::new());
+    }
+
+    #[test]
+    fn other_services_and_other_txt_data_are_ignored() {
+        let other_service = Name::from_ascii("printer._ipp._tcp.local.").unwrap();
+        let mut response = DnsMessage::new();
+        response
+            .set_message_type(MessageType::Response)
+            .add_answer(Record::from_rdata(
+                other_service,
+                120,
+                RData::TXT(TXT::new(vec!["descriptor=booga".to_string()])),
+            ))
+            .add_answer(Record::from_rdata(
+                Name::from_ascii("masq-0123abcd._masq._udp.local.").unwrap(),
+                120,
+                RData::TXT(TXT::new(vec!["txtvers=1".to_string()])),
+            ));
+        let mut other_query = DnsMessage::new();
+        other_query
+            .set_message_type(MessageType::Query)
+            .add_query(Query::query(
+                Name::from_ascii("_ipp._tcp.local.").unwrap(),
+                RecordType::PTR,
+            ));
+
+        assert_eq!(
+            descriptors_in(&response.to_vec().unwrap()),
+            Vec::<String>::new()
+        );
+        assert_eq!(is_service_query(&other_query.to_vec().unwrap()), false);
+        assert_eq!(descriptors_in(b"booga"), Vec::<String>::new());
+        assert_eq!(is_service_query(b"booga"), false);
+    }
+}
@@ -0,0 +1,249 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use flexi_logger::{LevelFilter, LogSpecBuilder, LogSpecification, ReconfigurationHandle};
+use lazy_static::lazy_static;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+#[cfg(not(target_os = "windows"))]
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard};
+#[cfg(not(target_os = "windows"))]
+use std::time::Duration;
+
+// These drown out everything else, so they stay quiet whatever anybody asks for
+const SILENCED_TARGETS: &[&str] = &["tokio", "mio"];
+
+#[cfg(not(target_os = "windows"))]
+const SIGHUP_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[cfg(not(target_os = "windows"))]
+static SIGHUP_RECEIVED: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+    static ref LOG_CONTROL: Mutex<LogControl> =
+        Mutex::new(LogControl::new(LogLevels::new(LevelFilter::Warn), None));
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogLevels {
+    pub default: LevelFilter,
+    // Logger names, like "Neighborhood", and the levels they log at instead of the default
+    pub targets: BTreeMap<String, LevelFilter>,
+}
+
+impl LogLevels {
+    pub fn new(default: LevelFilter) -> LogLevels {
+        LogLevels {
+            default,
+            targets: BTreeMap::new(),
+        }
+    }
+
+    pub fn spec(&self) -> LogSpecification {
+        let mut builder = LogSpecBuilder::new();
+        builder.default(self.default);
+        self.targets.iter().for_each(|(target, level)| {
+            builder.module(target, *level);
+        });
+        SILENCED_TARGETS.iter().for_each(|target| {
+            builder.module(target, LevelFilter::Off);
+        });
+        builder.build()
+    }
+}
+
+pub struct LogControl {
+    levels: LogLevels,
+    handle_opt: Option<ReconfigurationHandle>,
+    // What to go back to when trace logging is toggled off again
+    untraced_opt: Option<LogLevels>,
+}
+
+impl LogControl {
+    pub fn new(levels: LogLevels, handle_opt: Option<ReconfigurationHandle>) -> LogControl {
+        LogControl {
+            levels,
+            handle_opt,
+            untraced_opt: None,
+        }
+    }
+
+    pub fn levels(&self) -> LogLevels {
+        self.levels.clone()
+    }
+
+    // None leaves things as they are; new targets replace all the old ones
+    pub fn change(
+        &mut self,
+        default_opt: Option<LevelFilter>,
+        targets_opt: Option<BTreeMap<String, LevelFilter>>,
+    ) -> LogLevels {
+        let mut levels = self.levels.clone();
+        if let Some(default) = default_opt {
+            levels.default = default;
+        }
+        if let Some(targets) = targets_opt {
+            levels.targets = targets;
+        }
+        self.untraced_opt = None;
+        self.apply(levels)
+    }
+
+    // Trace everything, or go back to whatever was in force before
+    pub fn toggle_trace(&mut self) -> LogLevels {
+        match self.untraced_opt.take() {
+            Some(untraced) => self.apply(untraced),
+            None => {
+                self.untraced_opt = Some(self.levels.clone());
+                self.apply(LogLevels::new(LevelFilter::Trace))
+            }
+        }
+    }
+
+    fn apply(&mut self, levels: LogLevels) -> LogLevels {
+        if let Some(handle) = self.handle_opt.as_mut() {
+            handle.set_new_spec(levels.spec());
+        }
+        self.levels = levels;
+        self.levels.clone()
+    }
+}
+
+// The running logger, and the levels it started with
+pub fn take_control(handle: ReconfigurationHandle, levels: LogLevels) {
+    *log_control_guard() = LogControl::new(levels, Some(handle));
+}
+
+pub fn change_log_levels(
+    default_opt: Option<LevelFilter>,
+    targets_opt: Option<BTreeMap<String, LevelFilter>>,
+) -> LogLevels {
+    log_control_guard().change(default_opt, targets_opt)
+}
+
+pub fn toggle_trace() -> LogLevels {
+    log_control_guard().toggle_trace()
+}
+
+pub fn parse_level(name: &str) -> Result<LevelFilter, String> {
+    LevelFilter::from_str(name).map_err(|_| format!("'{}' is not a log level", name))
+}
+
+// Lets somebody without a UI turn the logging all the way up, and back down, with kill -HUP
+#[cfg(not(target_os = "windows"))]
+pub fn toggle_trace_on_sighup() {
+    use crate::sub_lib::logger::Logger;
+    use nix::sys::signal::{sigaction, SaFlags, SigAction, SigHandler, SigSet, Signal};
+    let logger = Logger::new("LogControl");
+    let action = SigAction::new(
+        SigHandler::Handler(note_sighup),
+        SaFlags::SA_RESTART,
+        SigSet::empty(),
+    );
+    // note_sighup does nothing but store to an atomic, which is safe in a signal handler
+    if let Err(e) = unsafe { sigaction(Signal::SIGHUP, &action) } {
+        warning!(logger, "Can't listen for SIGHUP: {}", e);
+        return;
+    }
+    std::thread::spawn(move || loop {
+        std::thread::sleep(SIGHUP_POLL_INTERVAL);
+        if SIGHUP_RECEIVED.swap(false, Ordering::Relaxed) {
+            let levels = toggle_trace();
+            warning!(
+                logger,
+                "SIGHUP received: now logging at {} with {} per-target levels",
+                levels.default,
+                levels.targets.len()
+            );
+        }
+    });
+}
+
+#[cfg(not(target_os = "windows"))]
+extern "C" fn note_sighup(_signal: nix::libc::c_int) {
+    SIGHUP_RECEIVED.store(true, Ordering::Relaxed);
+}
+
+fn log_control_guard<'a>() -> MutexGuard<'a, LogControl> {
+    match LOG_CONTROL.lock() {
+        Ok(guard) => guard,
+        Err(poison_err) => poison_err.into_inner(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn targets(pairs: Vec<(&str, LevelFilter)>) -> BTreeMap<String, LevelFilter> {
+        pairs
+            .into_iter()
+            .map(|(target, level)| (target.to_string(), level))
+            .collect()
+    }
+
+    #[test]
+    fn changes_keep_whatever_they_dont_mention() {
+        let mut subject = LogControl::new(LogLevels::new(LevelFilter::Warn), None);
+
+        let first = subject.change(
+            None,
+            Some(targets(vec![("Neighborhood", LevelFilter::Trace)])),
+        );
+        let second = subject.change(Some(LevelFilter::Info), None);
+        let third = subject.change(None, Some(BTreeMap::new()));
+
+        assert_eq!(
+            first,
+            LogLevels {
+                default: LevelFilter::Warn,
+                targets: targets(vec![("Neighborhood", LevelFilter::Trace)]),
+            }
+        );
+        assert_eq!(
+            second,
+            LogLevels {
+                default: LevelFilter::Info,
+                targets: targets(vec![("Neighborhood", LevelFilter::Trace)]),
+            }
+        );
+        assert_eq!(third, LogLevels::new(LevelFilter::Info));
+        assert_eq!(subject.levels(), third);
+    }
+
+    #[test]
+    fn toggling_trace_twice_restores_the_levels_before() {
+        let before = LogLevels {
+            default: LevelFilter::Warn,
+            targets: targets(vec![("Accountant", LevelFilter::Debug)]),
+        };
+        let mut subject = LogControl::new(before.clone(), None);
+
+        let traced = subject.toggle_trace();
+        let untraced = subject.toggle_trace();
+
+        assert_eq!(traced, LogLevels::new(LevelFilter::Trace));
+        assert_eq!(untraced, before);
+    }
+
+    #[test]
+    fn a_change_while_tracing_is_what_the_next_toggle_traces_from() {
+        let mut subject = LogControl::new(LogLevels::new(LevelFilter::Warn), None);
+        subject.toggle_trace();
+
+        subject.change(Some(LevelFilter::Error), None);
+        let result = subject.toggle_trace();
+
+        assert_eq!(result, LogLevels::new(LevelFilter::Trace));
+        assert_eq!(subject.toggle_trace(), LogLevels::new(LevelFilter::Error));
+    }
+
+    #[test]
+    fn levels_are_parsed_regardless_of_case() {
+        assert_eq!(parse_level("TRACE"), Ok(LevelFilter::Trace));
+        assert_eq!(parse_level("off"), Ok(LevelFilter::Off));
+        assert_eq!(
+            parse_level("loud"),
+            Err("'loud' is not a log level".to_string())
+        );
+    }
+}
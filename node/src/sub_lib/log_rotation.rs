@@ -0,0 +1,398 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use crate::sub_lib::logger::Logger;
+use chrono::{DateTime, Local};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use lazy_static::lazy_static;
+use masq_lib::constants::CURRENT_LOGFILE_NAME;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, Once};
+use std::time::{Duration, Instant, SystemTime};
+use std::{fs, thread};
+
+pub const DEFAULT_LOG_ROTATION_SIZE_MB: u64 = 100;
+pub const DEFAULT_LOG_ROTATION_AGE_HOURS: u64 = 0;
+pub const DEFAULT_LOG_RETENTION: u64 = 50;
+
+const ARCHIVE_PREFIX: &str = "MASQNode_";
+const ARCHIVE_SUFFIX: &str = ".log.gz";
+const ROTATION_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+lazy_static! {
+    static ref LOG_ROTATOR: Mutex<Option<LogRotator>> = Mutex::new(None);
+}
+static START_ROTATION_THREAD: Once = Once::new();
+
+// Zero for either limit means the log is never rotated on that account
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogRotationConfig {
+    pub size_mb: u64,
+    pub age_hours: u64,
+    pub retention: u64,
+}
+
+impl Default for LogRotationConfig {
+    fn default() -> Self {
+        LogRotationConfig {
+            size_mb: DEFAULT_LOG_ROTATION_SIZE_MB,
+            age_hours: DEFAULT_LOG_ROTATION_AGE_HOURS,
+            retention: DEFAULT_LOG_RETENTION,
+        }
+    }
+}
+
+// Rotation copies the current log into a compressed archive and then empties it in place, so the logger
+// never has to reopen its file. Anything written between the copy and the truncation is lost.
+pub struct LogRotator {
+    directory: PathBuf,
+    max_size: u64,
+    max_age_opt: Option<Duration>,
+    retention: usize,
+    last_rotation: Instant,
+}
+
+impl LogRotator {
+    pub fn new(directory: PathBuf, config: &LogRotationConfig) -> LogRotator {
+        LogRotator {
+            directory,
+            max_size: config.size_mb * 1_000_000,
+            max_age_opt: match config.age_hours {
+                0 => None,
+                hours => Some(Duration::from_secs(hours * 3600)),
+            },
+            retention: config.retention as usize,
+            last_rotation: Instant::now(),
+        }
+    }
+
+    pub fn rotate_if_due(&mut self) -> io::Result<Option<PathBuf>> {
+        let size = match fs::metadata(self.current_log()) {
+            Ok(metadata) => metadata.len(),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let too_big = self.max_size > 0 && size >= self.max_size;
+        let too_old = match self.max_age_opt {
+            Some(max_age) => self.last_rotation.elapsed() >= max_age,
+            None => false,
+        };
+        if too_big || too_old {
+            self.rotate()
+        } else {
+            Ok(None)
+        }
+    }
+
+    // Returns the new archive, unless there was nothing to put in one
+    pub fn rotate(&mut self) -> io::Result<Option<PathBuf>> {
+        self.last_rotation = Instant::now();
+        let current_log = self.current_log();
+        match fs::metadata(&current_log) {
+            Ok(ref metadata) if metadata.len() > 0 => (),
+            Ok(_) => return Ok(None),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        }
+        let archive = self.archive_path(SystemTime::now());
+        compress(&current_log, &archive)?;
+        OpenOptions::new()
+            .write(true)
+            .open(&current_log)?
+            .set_len(0)?;
+        self.enforce_retention()?;
+        Ok(Some(archive))
+    }
+
+    // The logger leaves numbered logs behind when it starts up; they're compressed like any other
+    pub fn archive_leftovers(&mut self) -> io::Result<()> {
+        let leftovers = fs::read_dir(&self.directory)?
+            .filter_map(|entry_result| entry_result.ok())
+            .map(|entry| entry.path())
+            .filter(|path| is_leftover(path))
+            .collect::<Vec<PathBuf>>();
+        for leftover in leftovers {
+            let written = fs::metadata(&leftover)?.modified()?;
+            compress(&leftover, &self.archive_path(written))?;
+            fs::remove_file(&leftover)?;
+        }
+        self.enforce_retention()
+    }
+
+    fn enforce_retention(&self) -> io::Result<()> {
+        let archives = archives_oldest_first(&self.directory)?;
+        let excess = archives.len().saturating_sub(self.retention);
+        archives
+            .into_iter()
+            .take(excess)
+            .try_for_each(fs::remove_file)
+    }
+
+    fn current_log(&self) -> PathBuf {
+        self.directory.join(CURRENT_LOGFILE_NAME)
+    }
+
+    fn archive_path(&self, written: SystemTime) -> PathBuf {
+        let timestamp = DateTime::<Local>::from(written).format("%Y-%m-%dT%H-%M-%S%.3f");
+        self.directory
+            .join(format!("{}{}{}", ARCHIVE_PREFIX, timestamp, ARCHIVE_SUFFIX))
+    }
+}
+
+// Rotates the Node's log, in the data directory, on a thread of its own
+pub fn start_log_rotation(directory: PathBuf, config: &LogRotationConfig) {
+    let mut rotator = LogRotator::new(directory, config);
+    if let Err(e) = rotator.archive_leftovers() {
+        let logger = Logger::new("LogRotator");
+        warning!(logger, "Couldn't archive old logs: {}", e);
+    }
+    *log_rotator_guard() = Some(rotator);
+    START_ROTATION_THREAD.call_once(|| {
+        thread::spawn(|| {
+            let logger = Logger::new("LogRotator");
+            loop {
+                thread::sleep(ROTATION_CHECK_INTERVAL);
+                if let Some(rotator) = log_rotator_guard().as_mut() {
+                    match rotator.rotate_if_due() {
+                        Ok(Some(archive)) => {
+                            info!(logger, "Rotated log into {}", archive.display())
+                        }
+                        Ok(None) => (),
+                        Err(e) => warning!(logger, "Couldn't rotate log: {}", e),
+                    }
+                }
+            }
+        });
+    });
+}
+
+pub fn rotate_log_now() -> Result<Option<PathBuf>, String> {
+    match log_rotator_guard().as_mut() {
+        Some(rotator) => rotator.rotate().map_err(|e| e.to_string()),
+        None => Err("Log rotation isn't running".to_string()),
+    }
+}
+
+// The newest archives in the directory, newest first
+pub fn recent_archives(directory: &Path, count: usize) -> io::Result<Vec<PathBuf>> {
+    Ok(archives_oldest_first(directory)?
+        .into_iter()
+        .rev()
+        .take(count)
+        .collect())
+}
+
+fn archives_oldest_first(directory: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut archives = fs::read_dir(directory)?
+        .filter_map(|entry_result| entry_result.ok())
+        .filter(|entry| is_archive(&entry.path()))
+        .map(|entry| {
+            let modified = entry
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .unwrap_or(SystemTime::UNIX_EPOCH);
+            (modified, entry.path())
+        })
+        .collect::<Vec<(SystemTime, PathBuf)>>();
+    archives.sort();
+    Ok(archives.into_iter().map(|(_, path)| path).collect())
+}
+
+fn compress(source: &Path, destination: &Path) -> io::Result<()> {
+    let mut encoder = GzEncoder::new(File::create(destination)?, Compression::default());
+    io::copy(&mut File::open(source)?, &mut encoder)?;
+    encoder.finish()?;
+    Ok(())
+}
+
+fn is_archive(path: &Path) -> bool {
+    file_name_of(path).map_or(false, |name| {
+        name.starts_with(ARCHIVE_PREFIX) && name.ends_with(ARCHIVE_SUFFIX)
+    })
+}
+
+// Numbered logs look like MASQNode_r00012.log
+fn is_leftover(path: &Path) -> bool {
+    file_name_of(path).map_or(false, |name| {
+        match name
+            .strip_prefix("MASQNode_r")
+            .and_then(|rest| rest.strip_suffix(".log"))
+        {
+            Some(number) => !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()),
+            None => false,
+        }
+    })
+}
+
+fn file_name_of(path: &Path) -> Option<&str> {
+    path.file_name().and_then(|name| name.to_str())
+}
+
+fn log_rotator_guard<'a>() -> MutexGuard<'a, Option<LogRotator>> {
+    match LOG_ROTATOR.lock() {
+        Ok(guard) => guard,
+        Err(poison_err) => poison_err.into_inner(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use masq_lib::test_utils::utils::ensure_node_home_directory_exists;
+    use std::io::Read;
+
+    fn archives_in(directory: &Path) -> Vec<PathBuf> {
+        let mut archives = fs::read_dir(directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| is_archive(path))
+            .collect::<Vec<PathBuf>>();
+        archives.sort();
+        archives
+    }
+
+    fn decompress(archive: &Path) -> String {
+        let mut contents = String::new();
+        GzDecoder::new(File::open(archive).unwrap())
+            .read_to_string(&mut contents)
+            .unwrap();
+        contents
+    }
+
+    #[test]
+    fn rotation_archives_the_log_and_empties_it() {
+        let directory = ensure_node_home_directory_exists(
+            "log_rotation",
+            "rotation_archives_the_log_and_empties_it",
+        );
+        fs::write(
+            directory.join(CURRENT_LOGFILE_NAME),
+            "First line\nSecond line\n",
+        )
+        .unwrap();
+        let mut subject = LogRotator::new(directory.clone(), &LogRotationConfig::default());
+
+        let result = subject.rotate().unwrap();
+
+        let archive = result.unwrap();
+        assert_eq!(archives_in(&directory), vec![archive.clone()]);
+        assert_eq!(decompress(&archive), "First line\nSecond line\n");
+        assert_eq!(
+            fs::metadata(directory.join(CURRENT_LOGFILE_NAME))
+                .unwrap()
+                .len(),
+            0
+        );
+    }
+
+    #[test]
+    fn an_empty_or_missing_log_isnt_archived() {
+        let directory = ensure_node_home_directory_exists(
+            "log_rotation",
+            "an_empty_or_missing_log_isnt_archived",
+        );
+        let mut subject = LogRotator::new(directory.clone(), &LogRotationConfig::default());
+
+        let missing = subject.rotate().unwrap();
+        fs::write(directory.join(CURRENT_LOGFILE_NAME), "").unwrap();
+        let empty = subject.rotate().unwrap();
+
+        assert_eq!(missing, None);
+        assert_eq!(empty, None);
+        assert_eq!(archives_in(&directory), Vec::<PathBuf>::new());
+    }
+
+    #[test]
+    fn rotation_is_due_when_the_log_gets_too_big() {
+        let directory = ensure_node_home_directory_exists(
+            "log_rotation",
+            "rotation_is_due_when_the_log_gets_too_big",
+        );
+        let mut subject = LogRotator::new(directory.clone(), &LogRotationConfig::default());
+        subject.max_size = 10;
+
+        fs::write(directory.join(CURRENT_LOGFILE_NAME), "123456789").unwrap();
+        let small = subject.rotate_if_due().unwrap();
+        fs::write(directory.join(CURRENT_LOGFILE_NAME), "1234567890").unwrap();
+        let big = subject.rotate_if_due().unwrap();
+
+        assert_eq!(small, None);
+        assert_eq!(big.is_some(), true);
+    }
+
+    #[test]
+    fn rotation_is_due_when_the_log_gets_too_old() {
+        let directory = ensure_node_home_directory_exists(
+            "log_rotation",
+            "rotation_is_due_when_the_log_gets_too_old",
+        );
+        fs::write(directory.join(CURRENT_LOGFILE_NAME), "booga").unwrap();
+        let mut subject = LogRotator::new(
+            directory.clone(),
+            &LogRotationConfig {
+                size_mb: 0,
+                age_hours: 1,
+                retention: 5,
+            },
+        );
+
+        let young = subject.rotate_if_due().unwrap();
+        subject.last_rotation = Instant::now() - Duration::from_secs(3600);
+        let old = subject.rotate_if_due().unwrap();
+
+        assert_eq!(young, None);
+        assert_eq!(old.is_some(), true);
+    }
+
+    #[test]
+    fn only_the_newest_archives_are_kept() {
+        let directory =
+            ensure_node_home_directory_exists("log_rotation", "only_the_newest_archives_are_kept");
+        let mut subject = LogRotator::new(
+            directory.clone(),
+            &LogRotationConfig {
+                size_mb: 0,
+                age_hours: 0,
+                retention: 2,
+            },
+        );
+
+        let archives = (0..3)
+            .map(|idx| {
+                fs::write(directory.join(CURRENT_LOGFILE_NAME), format!("log {}", idx)).unwrap();
+                thread::sleep(Duration::from_millis(10));
+                subject.rotate().unwrap().unwrap()
+            })
+            .collect::<Vec<PathBuf>>();
+
+        assert_eq!(archives_in(&directory), archives[1..].to_vec());
+        assert_eq!(decompress(&archives[2]), "log 2");
+        assert_eq!(
+            recent_archives(&directory, 1).unwrap(),
+            vec![archives[2].clone()]
+        );
+    }
+
+    #[test]
+    fn numbered_logs_left_behind_are_archived() {
+        let directory = ensure_node_home_directory_exists(
+            "log_rotation",
+            "numbered_logs_left_behind_are_archived",
+        );
+        fs::write(directory.join("MASQNode_r00003.log"), "Left behind").unwrap();
+        fs::write(directory.join(CURRENT_LOGFILE_NAME), "Current").unwrap();
+        fs::write(directory.join("MASQNode_rabc.log"), "Not a log").unwrap();
+        let mut subject = LogRotator::new(directory.clone(), &LogRotationConfig::default());
+
+        subject.archive_leftovers().unwrap();
+
+        let archives = archives_in(&directory);
+        assert_eq!(archives.len(), 1);
+        assert_eq!(decompress(&archives[0]), "Left behind");
+        assert_eq!(directory.join("MASQNode_r00003.log").exists(), false);
+        assert_eq!(directory.join(CURRENT_LOGFILE_NAME).exists(), true);
+        assert_eq!(directory.join("MASQNode_rabc.log").exists(), true);
+    }
+}
@@ -1,9 +1,62 @@
 // Copyright (c) 2017-2019, Substratum LLC (https://substratum.net) and/or its affiliates. All rights reserved.
+use actix::Recipient;
+use chrono::Local;
+use lazy_static::lazy_static;
 use log::logger;
 use log::Level;
 #[cfg(not(test))]
 use log::Metadata;
 use log::Record;
+use masq_lib::messages::{ToMessageBody, UiLogsBroadcast};
+use masq_lib::ui_gateway::MessageTarget::AllClients;
+use masq_lib::ui_gateway::NodeToUiMessage;
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+// Records from these would come back around to the UiGateway and be logged again, forever
+const UNFORWARDABLE_SOURCES: &[&str] = &["UiGateway", "WebSocketSupervisor"];
+
+lazy_static! {
+    static ref UI_LOG_SUB: Mutex<Option<Recipient<NodeToUiMessage>>> = Mutex::new(None);
+}
+// The least severe Level, as a usize, that any UI has subscribed to; 0 if none has
+static UI_LOG_LEVEL_LIMIT: AtomicUsize = AtomicUsize::new(0);
+
+// Where log records go, besides the log file, for UIs that subscribe to them
+pub fn set_ui_log_sub(sub_opt: Option<Recipient<NodeToUiMessage>>) {
+    *UI_LOG_SUB.lock().expect("UI log sub is poisoned") = sub_opt;
+}
+
+pub fn set_ui_log_level_limit(level_opt: Option<Level>) {
+    UI_LOG_LEVEL_LIMIT.store(
+        level_opt.map(|level| level as usize).unwrap_or(0),
+        Ordering::Relaxed,
+    );
+}
+
+fn ui_wants(level: Level) -> bool {
+    (level as usize) <= UI_LOG_LEVEL_LIMIT.load(Ordering::Relaxed)
+}
+
+thread_local! {
+    static CORRELATION_ID: RefCell<Option<String>> = RefCell::new(None);
+}
+
+// Tags every record this thread logs while f runs, so that a UI can tell which records belong together
+pub fn with_correlation_id<T, F>(correlation_id: String, f: F) -> T
+where
+    F: FnOnce() -> T,
+{
+    let previous_opt = CORRELATION_ID.with(|cell| cell.replace(Some(correlation_id)));
+    let result = f();
+    CORRELATION_ID.with(|cell| cell.replace(previous_opt));
+    result
+}
+
+fn correlation_id_opt() -> Option<String> {
+    CORRELATION_ID.with(|cell| cell.borrow().clone())
+}
 
 #[derive(Clone)]
 pub struct Logger {
@@ -135,7 +188,7 @@ impl Logger {
     where
         F: FnOnce() -> String,
     {
-        if !self.level_enabled(level) {
+        if !self.level_enabled(level) && !ui_wants(level) {
             return;
         }
         let string = log_function();
@@ -147,9 +200,36 @@ impl Logger {
             &Record::builder()
                 .args(format_args!("{}", msg))
                 .module_path(Some(&self.name))
+                .target(&self.name)
                 .level(level)
                 .build(),
         );
+        if ui_wants(level) {
+            self.forward_to_ui(level, msg);
+        }
+    }
+
+    fn forward_to_ui(&self, level: Level, msg: String) {
+        if UNFORWARDABLE_SOURCES.contains(&self.name.as_str()) {
+            return;
+        }
+        if let Some(sub) = UI_LOG_SUB.lock().expect("UI log sub is poisoned").as_ref() {
+            // A UiGateway that's gone is no reason to stop logging
+            let _ = sub.try_send(NodeToUiMessage {
+                target: AllClients,
+                body: UiLogsBroadcast {
+                    level: level.to_string(),
+                    target: self.name.clone(),
+                    message: msg,
+                    timestamp: Local::now()
+                        .naive_local()
+                        .format("%Y-%m-%dT%H:%M:%S%.3f")
+                        .to_string(),
+                    correlation_id_opt: correlation_id_opt(),
+                }
+                .tmb(0),
+            });
+        }
     }
 }
 
@@ -193,6 +273,21 @@ mod tests {
         assert_between(&another_log[..prefix_len], &before_str, &after_str);
     }
 
+    #[test]
+    fn correlation_ids_last_as_long_as_their_scopes() {
+        let outside_before = correlation_id_opt();
+        let (outer, inner) = with_correlation_id("outer".to_string(), || {
+            let inner = with_correlation_id("inner".to_string(), correlation_id_opt);
+            (correlation_id_opt(), inner)
+        });
+        let outside_after = correlation_id_opt();
+
+        assert_eq!(outside_before, None);
+        assert_eq!(outer, Some("outer".to_string()));
+        assert_eq!(inner, Some("inner".to_string()));
+        assert_eq!(outside_after, None);
+    }
+
     #[test]
     fn trace_is_not_computed_when_log_level_is_debug() {
         let logger = Logger {
@@ -0,0 +1,217 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MetricKind {
+    Counter,
+    Gauge,
+}
+
+impl MetricKind {
+    fn name(self) -> &'static str {
+        match self {
+            MetricKind::Counter => "counter",
+            MetricKind::Gauge => "gauge",
+        }
+    }
+}
+
+// Actors update these as they work; the metrics exporter reads them whenever it's scraped
+pub struct Metric {
+    pub name: &'static str,
+    pub help: &'static str,
+    pub kind: MetricKind,
+    value: AtomicU64,
+}
+
+impl Metric {
+    pub const fn new(name: &'static str, help: &'static str, kind: MetricKind) -> Metric {
+        Metric {
+            name,
+            help,
+            kind,
+            value: AtomicU64::new(0),
+        }
+    }
+
+    pub fn add(&self, amount: u64) {
+        self.value.fetch_add(amount, Ordering::Relaxed);
+    }
+
+    pub fn set(&self, value: u64) {
+        self.value.store(value, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.value.load(Ordering::Relaxed)
+    }
+}
+
+pub static ROUTED_BYTES: Metric = Metric::new(
+    "masq_routed_bytes_total",
+    "Bytes this Node has relayed for other Nodes that paid for the routing",
+    MetricKind::Counter,
+);
+pub static EXIT_BYTES: Metric = Metric::new(
+    "masq_exit_bytes_total",
+    "Bytes this Node has carried between servers and other Nodes that paid for exit service",
+    MetricKind::Counter,
+);
+pub static ORIGINATING_STREAMS: Metric = Metric::new(
+    "masq_originating_streams",
+    "Streams this Node's own clients have open through the MASQ Network",
+    MetricKind::Gauge,
+);
+pub static SERVED_STREAMS: Metric = Metric::new(
+    "masq_served_streams",
+    "Streams this Node has open to servers as the exit for other Nodes",
+    MetricKind::Gauge,
+);
+pub static NEIGHBORS: Metric = Metric::new(
+    "masq_neighbors",
+    "Nodes this Node is fully connected to",
+    MetricKind::Gauge,
+);
+pub static NEIGHBORS_GAINED: Metric = Metric::new(
+    "masq_neighbors_gained_total",
+    "Nodes this Node has become fully connected to",
+    MetricKind::Counter,
+);
+pub static NEIGHBORS_LOST: Metric = Metric::new(
+    "masq_neighbors_lost_total",
+    "Nodes this Node has stopped being fully connected to",
+    MetricKind::Counter,
+);
+pub static EARNED_GWEI: Metric = Metric::new(
+    "masq_earned_gwei_total",
+    "Gwei this Node has charged other Nodes for routing and exit service",
+    MetricKind::Counter,
+);
+pub static PAYABLES_GWEI: Metric = Metric::new(
+    "masq_payables_gwei",
+    "Gwei this Node owes other Nodes, not counting payments on their way, as of the last payable scan",
+    MetricKind::Gauge,
+);
+pub static GOSSIP_RECEIVED: Metric = Metric::new(
+    "masq_gossip_received_total",
+    "Gossip messages this Node has received from its neighbors",
+    MetricKind::Counter,
+);
+pub static GOSSIP_SENT: Metric = Metric::new(
+    "masq_gossip_sent_total",
+    "Gossip messages this Node has sent to its neighbors",
+    MetricKind::Counter,
+);
+pub static REPLAYED_PACKAGES: Metric = Metric::new(
+    "masq_replayed_packages_total",
+    "CORES packages this Node has refused because it had already received them",
+    MetricKind::Counter,
+);
+pub static STALE_PACKAGES: Metric = Metric::new(
+    "masq_stale_packages_total",
+    "CORES packages this Node has refused because they were stamped too long before or after its own clock",
+    MetricKind::Counter,
+);
+pub static HELD_PACKAGES: Metric = Metric::new(
+    "masq_held_packages_total",
+    "CORES packages this Node held back from the next hop because the neighbor they came from already had a full window waiting for it",
+    MetricKind::Counter,
+);
+pub static DNS_CACHE_HITS: Metric = Metric::new(
+    "masq_dns_cache_hits_total",
+    "Exit look-ups this Node answered with addresses from its DNS cache",
+    MetricKind::Counter,
+);
+pub static DNS_CACHE_NEGATIVE_HITS: Metric = Metric::new(
+    "masq_dns_cache_negative_hits_total",
+    "Exit look-ups this Node answered from its DNS cache with a host that doesn't exist",
+    MetricKind::Counter,
+);
+pub static DNS_CACHE_MISSES: Metric = Metric::new(
+    "masq_dns_cache_misses_total",
+    "Exit look-ups this Node had to ask a DNS server about",
+    MetricKind::Counter,
+);
+pub static DNS_CACHE_EVICTIONS: Metric = Metric::new(
+    "masq_dns_cache_evictions_total",
+    "Unexpired answers this Node dropped from its full DNS cache to make room for new ones",
+    MetricKind::Counter,
+);
+pub static DNS_CACHE_ENTRIES: Metric = Metric::new(
+    "masq_dns_cache_entries",
+    "Answers in this Node's DNS cache",
+    MetricKind::Gauge,
+);
+
+pub static ALL_METRICS: &[&Metric] = &[
+    &ROUTED_BYTES,
+    &EXIT_BYTES,
+    &ORIGINATING_STREAMS,
+    &SERVED_STREAMS,
+    &NEIGHBORS,
+    &NEIGHBORS_GAINED,
+    &NEIGHBORS_LOST,
+    &EARNED_GWEI,
+    &PAYABLES_GWEI,
+    &GOSSIP_RECEIVED,
+    &GOSSIP_SENT,
+    &REPLAYED_PACKAGES,
+    &STALE_PACKAGES,
+    &HELD_PACKAGES,
+    &DNS_CACHE_HITS,
+    &DNS_CACHE_NEGATIVE_HITS,
+    &DNS_CACHE_MISSES,
+    &DNS_CACHE_EVICTIONS,
+    &DNS_CACHE_ENTRIES,
+];
+
+// Prometheus text exposition format, version 0.0.4
+pub fn render(metrics: &[&Metric]) -> String {
+    metrics.iter().fold(String::new(), |mut text, metric| {
+        writeln!(text, "# HELP {} {}", metric.name, metric.help).expect("String write failed");
+        writeln!(text, "# TYPE {} {}", metric.name, metric.kind.name())
+            .expect("String write failed");
+        writeln!(text, "{} {}", metric.name, metric.get()).expect("String write failed");
+        text
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counters_accumulate_and_gauges_are_replaced() {
+        let counter = Metric::new("counter", "A counter", MetricKind::Counter);
+        let gauge = Metric::new("gauge", "A gauge", MetricKind::Gauge);
+
+        counter.add(3);
+        counter.add(4);
+        gauge.set(3);
+        gauge.set(4);
+
+        assert_eq!(counter.get(), 7);
+        assert_eq!(gauge.get(), 4);
+    }
+
+    #[test]
+    fn metrics_are_rendered_in_prometheus_text_format() {
+        let counter = Metric::new("masq_things_total", "Things so far", MetricKind::Counter);
+        let gauge = Metric::new("masq_things", "Things now", MetricKind::Gauge);
+        counter.add(42);
+        gauge.set(7);
+
+        let result = render(&[&counter, &gauge]);
+
+        assert_eq!(
+            result,
+            "# HELP masq_things_total Things so far\n\
+             # TYPE masq_things_total counter\n\
+             masq_things_total 42\n\
+             # HELP masq_things Things now\n\
+             # TYPE masq_things gauge\n\
+             masq_things 7\n"
+        );
+    }
+}
@@ -0,0 +1,90 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+
+use crate::sub_lib::migrations::utils::value_to_type;
+use crate::sub_lib::neighborhood::Benchmark_0v1;
+use crate::sub_lib::versioned_data::Migrations;
+use crate::sub_lib::versioned_data::FUTURE_VERSION;
+use crate::sub_lib::versioned_data::{MigrationError, StepError, VersionedData};
+use lazy_static::lazy_static;
+use serde_cbor::Value;
+use std::convert::TryFrom;
+
+lazy_static! {
+    pub static ref MIGRATIONS: Migrations = {
+        let current_version = dv!(0, 1);
+        let mut migrations = Migrations::new(current_version);
+
+        migrate_value!(dv!(0, 1), Benchmark_0v1, BenchmarkMF_0v1, {|value: serde_cbor::Value| {
+            Benchmark_0v1::try_from (&value)
+        }});
+        migrations.add_step (FUTURE_VERSION, dv!(0, 1), Box::new (BenchmarkMF_0v1{}));
+
+        // add more steps here
+
+        migrations
+    };
+}
+
+impl Into<VersionedData<Benchmark_0v1>> for Benchmark_0v1 {
+    fn into(self) -> VersionedData<Benchmark_0v1> {
+        VersionedData::new(&MIGRATIONS, &self)
+    }
+}
+
+impl TryFrom<VersionedData<Benchmark_0v1>> for Benchmark_0v1 {
+    type Error = MigrationError;
+
+    fn try_from(vd: VersionedData<Benchmark_0v1>) -> Result<Self, Self::Error> {
+        vd.extract(&MIGRATIONS)
+    }
+}
+
+impl TryFrom<&Value> for Benchmark_0v1 {
+    type Error = StepError;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        match value_to_type::<Benchmark_0v1>(value) {
+            Some(benchmark) => Ok(benchmark),
+            None => Err(StepError::SemanticError(format!(
+                "Expected Benchmark_0v1; found {:?}",
+                value
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sub_lib::cryptde::PlainData;
+
+    #[test]
+    fn can_migrate_from_the_present() {
+        let probe = Benchmark_0v1::Probe {
+            nonce: 1234,
+            padding: PlainData::new(&[0u8; 16]),
+        };
+        let vd: VersionedData<Benchmark_0v1> = probe.clone().into();
+        let serialized = serde_cbor::ser::to_vec(&vd).unwrap();
+        let deserialized =
+            serde_cbor::de::from_slice::<VersionedData<Benchmark_0v1>>(&serialized).unwrap();
+
+        let actual = Benchmark_0v1::try_from(deserialized).unwrap();
+
+        assert_eq!(actual, probe);
+    }
+
+    #[test]
+    fn cannot_migrate_from_value_that_is_not_a_benchmark() {
+        let value = Value::Bool(true);
+
+        let result = Benchmark_0v1::try_from(&value);
+
+        assert_eq!(
+            result,
+            Err(StepError::SemanticError(
+                "Expected Benchmark_0v1; found Bool(true)".to_string()
+            ))
+        )
+    }
+}
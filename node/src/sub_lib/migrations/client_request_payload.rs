@@ -54,6 +54,7 @@ impl TryFrom<&Value> for ClientRequestPayload_0v1 {
                 let mut target_port_opt: Option<u16> = None;
                 let mut protocol_opt: Option<ProxyProtocol> = None;
                 let mut originator_public_key_opt: Option<PublicKey> = None;
+                let mut is_resumption = false;
                 map.keys().for_each(|k| {
                     let v = map.get(k).expect("Disappeared");
                     if let (Value::Text(field_name), _) = (k, v) {
@@ -70,6 +71,9 @@ impl TryFrom<&Value> for ClientRequestPayload_0v1 {
                             "originator_public_key" => {
                                 originator_public_key_opt = value_to_type::<PublicKey>(v)
                             }
+                            "is_resumption" => {
+                                is_resumption = value_to_type::<bool>(v).unwrap_or(false)
+                            }
                             _ => (),
                         }
                     }
@@ -99,7 +103,10 @@ impl TryFrom<&Value> for ClientRequestPayload_0v1 {
                     &originator_public_key_opt,
                 );
                 if !missing_fields.is_empty() {
-                    unimplemented!("{:?}", missing_fields.clone())
+                    return Err(StepError::SemanticError(format!(
+                        "Missing fields: {:?}",
+                        missing_fields
+                    )));
                 }
                 Ok(ClientRequestPayload_0v1 {
                     stream_key: stream_key_opt.expect("stream_key disappeared"),
@@ -109,6 +116,7 @@ impl TryFrom<&Value> for ClientRequestPayload_0v1 {
                     protocol: protocol_opt.expect("protocol disappeared"),
                     originator_public_key: originator_public_key_opt
                         .expect("originator_public_key disappeared"),
+                    is_resumption,
                 })
             }
             _ => Err(StepError::SemanticError(format!(
@@ -151,6 +159,7 @@ mod tests {
             target_port: 1234,
             protocol: ProxyProtocol::HTTP,
             originator_public_key: PublicKey::new(&[2, 3, 4, 5]),
+            is_resumption: false,
         };
         let future_crp = ExampleFutureCRP {
             stream_key: expected_crp.stream_key.clone(),
@@ -174,6 +183,45 @@ mod tests {
         assert_eq!(actual_crp, expected_crp);
     }
 
+    #[test]
+    fn can_migrate_resumption_from_the_future() {
+        #[derive(Serialize, Deserialize)]
+        struct ExampleFutureCRP {
+            pub stream_key: StreamKey,
+            pub sequenced_packet: SequencedPacket,
+            pub target_hostname: Option<String>,
+            pub target_port: u16,
+            pub protocol: ProxyProtocol,
+            pub originator_public_key: PublicKey,
+            pub is_resumption: bool,
+            pub another_field: String,
+        }
+        let future_crp = ExampleFutureCRP {
+            stream_key: StreamKey::new(
+                PublicKey::new(&[1, 2, 3, 4]),
+                SocketAddr::from_str("1.2.3.4:1234").unwrap(),
+            ),
+            sequenced_packet: SequencedPacket::new(vec![4, 3, 2, 1], 4321, false),
+            target_hostname: Some("target.hostname.com".to_string()),
+            target_port: 80,
+            protocol: ProxyProtocol::HTTP,
+            originator_public_key: PublicKey::new(&[2, 3, 4, 5]),
+            is_resumption: true,
+            another_field: "These are the times that try men's souls".to_string(),
+        };
+        let future_migrations = Migrations::new(DataVersion::new(4095, 4095));
+        let serialized =
+            serde_cbor::ser::to_vec(&VersionedData::new(&future_migrations, &future_crp)).unwrap();
+        let future_vd =
+            serde_cbor::de::from_slice::<VersionedData<ClientRequestPayload_0v1>>(&serialized)
+                .unwrap();
+
+        let actual_crp = ClientRequestPayload_0v1::try_from(future_vd).unwrap();
+
+        assert_eq!(actual_crp.is_resumption, true);
+        assert_eq!(actual_crp.sequenced_packet.sequence_number, 4321);
+    }
+
     #[test]
     fn cannot_migrate_from_value_other_than_map() {
         let value = Value::Bool(true);
@@ -187,4 +235,42 @@ mod tests {
             ))
         )
     }
+
+    #[test]
+    fn truncated_records_from_the_future_are_errors() {
+        #[derive(Serialize, Deserialize)]
+        struct ExampleTruncatedCRP {
+            pub another_field: String,
+        }
+        let truncated = ExampleTruncatedCRP {
+            another_field: "These are the times that try men's souls".to_string(),
+        };
+        let future_migrations = Migrations::new(DataVersion::new(4095, 4095));
+        let short_vd = serde_cbor::de::from_slice::<VersionedData<ClientRequestPayload_0v1>>(
+            &serde_cbor::ser::to_vec(&VersionedData::new(&future_migrations, &truncated)).unwrap(),
+        )
+        .unwrap();
+        let bytes = serde_cbor::ser::to_vec(&truncated).unwrap();
+        let cut_vd = VersionedData::<ClientRequestPayload_0v1>::test_new(
+            DataVersion::new(4095, 4095),
+            bytes[..bytes.len() / 2].to_vec(),
+        );
+
+        assert_eq!(
+            ClientRequestPayload_0v1::try_from(short_vd),
+            Err(MigrationError::MigrationFailed(StepError::SemanticError(
+                "Missing fields: [\"stream_key\", \"sequenced_packet\", \"target_hostname\", \"target_port\", \"protocol\", \"originator_public_key\"]".to_string()
+            )))
+        );
+        assert_eq!(
+            ClientRequestPayload_0v1::try_from(cut_vd),
+            Err(MigrationError::MigrationFailed(
+                StepError::DeserializationError(
+                    FUTURE_VERSION,
+                    DataVersion::new(0, 1),
+                    "Wampum".to_string()
+                )
+            ))
+        );
+    }
 }
@@ -0,0 +1,214 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+
+use crate::sub_lib::cryptde::{PlainData, PublicKey};
+use crate::sub_lib::migrations::utils::value_to_type;
+use crate::sub_lib::proxy_server::ClientUdpRequestPayload_0v1;
+use crate::sub_lib::stream_key::StreamKey;
+use crate::sub_lib::versioned_data::Migrations;
+use crate::sub_lib::versioned_data::FUTURE_VERSION;
+use crate::sub_lib::versioned_data::{MigrationError, StepError, VersionedData};
+use lazy_static::lazy_static;
+use serde_cbor::Value;
+use std::convert::TryFrom;
+
+lazy_static! {
+    pub static ref MIGRATIONS: Migrations = {
+        let current_version = dv!(0, 1);
+        let mut migrations = Migrations::new(current_version);
+
+        migrate_value!(dv!(0, 1), ClientUdpRequestPayload_0v1, ClientUdpRequestPayloadMF_0v1, {|value: serde_cbor::Value| {
+            ClientUdpRequestPayload_0v1::try_from (&value)
+        }});
+        migrations.add_step (FUTURE_VERSION, dv!(0, 1), Box::new (ClientUdpRequestPayloadMF_0v1{}));
+
+        // add more steps here
+
+        migrations
+    };
+}
+
+impl Into<VersionedData<ClientUdpRequestPayload_0v1>> for ClientUdpRequestPayload_0v1 {
+    fn into(self) -> VersionedData<ClientUdpRequestPayload_0v1> {
+        VersionedData::new(&MIGRATIONS, &self)
+    }
+}
+
+impl TryFrom<VersionedData<ClientUdpRequestPayload_0v1>> for ClientUdpRequestPayload_0v1 {
+    type Error = MigrationError;
+
+    fn try_from(vd: VersionedData<ClientUdpRequestPayload_0v1>) -> Result<Self, Self::Error> {
+        vd.extract(&MIGRATIONS)
+    }
+}
+
+impl TryFrom<&Value> for ClientUdpRequestPayload_0v1 {
+    type Error = StepError;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Map(map) => {
+                let mut stream_key_opt: Option<StreamKey> = None;
+                let mut target_hostname_opt: Option<String> = None;
+                let mut target_port_opt: Option<u16> = None;
+                let mut data_opt: Option<PlainData> = None;
+                let mut originator_public_key_opt: Option<PublicKey> = None;
+                map.keys().for_each(|k| {
+                    let v = map.get(k).expect("Disappeared");
+                    if let Value::Text(field_name) = k {
+                        match field_name.as_str() {
+                            "stream_key" => stream_key_opt = value_to_type::<StreamKey>(v),
+                            "target_hostname" => target_hostname_opt = value_to_type::<String>(v),
+                            "target_port" => target_port_opt = value_to_type::<u16>(v),
+                            "data" => data_opt = value_to_type::<PlainData>(v),
+                            "originator_public_key" => {
+                                originator_public_key_opt = value_to_type::<PublicKey>(v)
+                            }
+                            _ => (),
+                        }
+                    }
+                });
+                let mut missing_fields: Vec<&str> = vec![];
+                fn check_field<'a, T>(
+                    missing_fields: &mut Vec<&'a str>,
+                    name: &'a str,
+                    field: &Option<T>,
+                ) {
+                    if field.is_none() {
+                        missing_fields.push(name)
+                    }
+                }
+                check_field(&mut missing_fields, "stream_key", &stream_key_opt);
+                check_field(&mut missing_fields, "target_hostname", &target_hostname_opt);
+                check_field(&mut missing_fields, "target_port", &target_port_opt);
+                check_field(&mut missing_fields, "data", &data_opt);
+                check_field(
+                    &mut missing_fields,
+                    "originator_public_key",
+                    &originator_public_key_opt,
+                );
+                if !missing_fields.is_empty() {
+                    return Err(StepError::SemanticError(format!(
+                        "Missing fields: {:?}",
+                        missing_fields
+                    )));
+                }
+                Ok(ClientUdpRequestPayload_0v1 {
+                    stream_key: stream_key_opt.expect("stream_key disappeared"),
+                    target_hostname: target_hostname_opt.expect("target_hostname disappeared"),
+                    target_port: target_port_opt.expect("target_port disappeared"),
+                    data: data_opt.expect("data disappeared"),
+                    originator_public_key: originator_public_key_opt
+                        .expect("originator_public_key disappeared"),
+                })
+            }
+            _ => Err(StepError::SemanticError(format!(
+                "Expected Value::Map; found {:?}",
+                value
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sub_lib::versioned_data::DataVersion;
+    use serde_derive::{Deserialize, Serialize};
+    use std::net::SocketAddr;
+    use std::str::FromStr;
+
+    #[test]
+    fn can_migrate_from_the_future() {
+        #[derive(Serialize, Deserialize)]
+        struct ExampleFutureCURP {
+            pub stream_key: StreamKey,
+            pub target_hostname: String,
+            pub target_port: u16,
+            pub data: PlainData,
+            pub originator_public_key: PublicKey,
+            pub another_field: String,
+            pub yet_another_field: u64,
+        }
+        let expected_curp = ClientUdpRequestPayload_0v1 {
+            stream_key: StreamKey::new(
+                PublicKey::new(&[1, 2, 3, 4]),
+                SocketAddr::from_str("1.2.3.4:1234").unwrap(),
+            ),
+            target_hostname: "target.hostname.com".to_string(),
+            target_port: 443,
+            data: PlainData::new(&[4, 3, 2, 1]),
+            originator_public_key: PublicKey::new(&[2, 3, 4, 5]),
+        };
+        let future_curp = ExampleFutureCURP {
+            stream_key: expected_curp.stream_key,
+            target_hostname: expected_curp.target_hostname.clone(),
+            target_port: expected_curp.target_port,
+            data: expected_curp.data.clone(),
+            originator_public_key: expected_curp.originator_public_key.clone(),
+            another_field: "These are the times that try men's souls".to_string(),
+            yet_another_field: 1234567890,
+        };
+        let future_migrations = Migrations::new(DataVersion::new(4095, 4095));
+        let serialized =
+            serde_cbor::ser::to_vec(&VersionedData::new(&future_migrations, &future_curp)).unwrap();
+        let future_vd =
+            serde_cbor::de::from_slice::<VersionedData<ClientUdpRequestPayload_0v1>>(&serialized)
+                .unwrap();
+
+        let actual_curp = ClientUdpRequestPayload_0v1::try_from(future_vd).unwrap();
+
+        assert_eq!(actual_curp, expected_curp);
+    }
+
+    #[test]
+    fn cannot_migrate_from_value_other_than_map() {
+        let value = Value::Bool(true);
+
+        let result = ClientUdpRequestPayload_0v1::try_from(&value);
+
+        assert_eq!(
+            result,
+            Err(StepError::SemanticError(
+                "Expected Value::Map; found Bool(true)".to_string()
+            ))
+        )
+    }
+
+    #[test]
+    fn truncated_records_from_the_future_are_errors() {
+        #[derive(Serialize, Deserialize)]
+        struct ExampleTruncatedCURP {
+            pub another_field: String,
+        }
+        let truncated = ExampleTruncatedCURP {
+            another_field: "These are the times that try men's souls".to_string(),
+        };
+        let future_migrations = Migrations::new(DataVersion::new(4095, 4095));
+        let short_vd = serde_cbor::de::from_slice::<VersionedData<ClientUdpRequestPayload_0v1>>(
+            &serde_cbor::ser::to_vec(&VersionedData::new(&future_migrations, &truncated)).unwrap(),
+        )
+        .unwrap();
+        let bytes = serde_cbor::ser::to_vec(&truncated).unwrap();
+        let cut_vd = VersionedData::<ClientUdpRequestPayload_0v1>::test_new(
+            DataVersion::new(4095, 4095),
+            bytes[..bytes.len() / 2].to_vec(),
+        );
+
+        assert_eq!(
+            ClientUdpRequestPayload_0v1::try_from(short_vd),
+            Err(MigrationError::MigrationFailed(StepError::SemanticError(
+                "Missing fields: [\"stream_key\", \"target_hostname\", \"target_port\", \"data\", \"originator_public_key\"]".to_string()
+            )))
+        );
+        assert_eq!(
+            ClientUdpRequestPayload_0v1::try_from(cut_vd),
+            Err(MigrationError::MigrationFailed(
+                StepError::DeserializationError(
+                    FUTURE_VERSION,
+                    DataVersion::new(0, 1),
+                    "Wampum".to_string()
+                )
+            ))
+        );
+    }
+}
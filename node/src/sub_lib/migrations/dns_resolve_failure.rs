@@ -67,7 +67,10 @@ impl TryFrom<&Value> for DnsResolveFailure_0v1 {
                 }
                 check_field(&mut missing_fields, "stream_key", &stream_key_opt);
                 if !missing_fields.is_empty() {
-                    unimplemented!("{:?}", missing_fields.clone())
+                    return Err(StepError::SemanticError(format!(
+                        "Missing fields: {:?}",
+                        missing_fields
+                    )));
                 }
                 Ok(DnsResolveFailure_0v1 {
                     stream_key: stream_key_opt.expect("stream_key disappeared"),
@@ -134,4 +137,42 @@ mod tests {
             ))
         )
     }
+
+    #[test]
+    fn truncated_records_from_the_future_are_errors() {
+        #[derive(Serialize, Deserialize)]
+        struct ExampleTruncatedDRF {
+            pub another_field: String,
+        }
+        let truncated = ExampleTruncatedDRF {
+            another_field: "These are the times that try men's souls".to_string(),
+        };
+        let future_migrations = Migrations::new(DataVersion::new(4095, 4095));
+        let short_vd = serde_cbor::de::from_slice::<VersionedData<DnsResolveFailure_0v1>>(
+            &serde_cbor::ser::to_vec(&VersionedData::new(&future_migrations, &truncated)).unwrap(),
+        )
+        .unwrap();
+        let bytes = serde_cbor::ser::to_vec(&truncated).unwrap();
+        let cut_vd = VersionedData::<DnsResolveFailure_0v1>::test_new(
+            DataVersion::new(4095, 4095),
+            bytes[..bytes.len() / 2].to_vec(),
+        );
+
+        assert_eq!(
+            DnsResolveFailure_0v1::try_from(short_vd),
+            Err(MigrationError::MigrationFailed(StepError::SemanticError(
+                "Missing fields: [\"stream_key\"]".to_string()
+            )))
+        );
+        assert_eq!(
+            DnsResolveFailure_0v1::try_from(cut_vd),
+            Err(MigrationError::MigrationFailed(
+                StepError::DeserializationError(
+                    FUTURE_VERSION,
+                    DataVersion::new(0, 1),
+                    "Wampum".to_string()
+                )
+            ))
+        );
+    }
 }
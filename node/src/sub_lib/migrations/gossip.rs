@@ -75,7 +75,10 @@ impl TryFrom<&Value> for Gossip_0v1 {
                     None => Ok(Gossip_0v1 { node_records }),
                 }
             }
-            _ => unimplemented!(), //Err (StepError::SemanticError("Inscrutable future version".to_string())),
+            _ => Err(StepError::SemanticError(format!(
+                "Expected Value::Map; found {:?}",
+                value
+            ))),
         }
     }
 }
@@ -121,4 +124,45 @@ mod tests {
 
         assert_eq!(actual_gossip, expected_gossip);
     }
+
+    #[test]
+    fn a_truncated_gossip_from_the_future_is_an_error() {
+        #[derive(Serialize, Deserialize)]
+        struct ExampleTruncatedGossip {
+            pub another_field: String,
+        }
+        let truncated = ExampleTruncatedGossip {
+            another_field: "These are the times that try men's souls".to_string(),
+        };
+        let bytes = serde_cbor::ser::to_vec(&truncated).unwrap();
+        let cut_vd = VersionedData::<Gossip_0v1>::test_new(
+            DataVersion::new(4095, 4095),
+            bytes[..bytes.len() / 2].to_vec(),
+        );
+
+        assert_eq!(
+            Gossip_0v1::try_from(cut_vd),
+            Err(MigrationError::MigrationFailed(
+                StepError::DeserializationError(
+                    FUTURE_VERSION,
+                    DataVersion::new(0, 1),
+                    "Wampum".to_string()
+                )
+            ))
+        );
+    }
+
+    #[test]
+    fn cannot_migrate_from_value_other_than_map() {
+        let value = Value::Bool(true);
+
+        let result = Gossip_0v1::try_from(&value);
+
+        assert_eq!(
+            result,
+            Err(StepError::SemanticError(
+                "Expected Value::Map; found Bool(true)".to_string()
+            ))
+        )
+    }
 }
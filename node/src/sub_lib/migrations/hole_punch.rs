@@ -0,0 +1,92 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+
+use crate::sub_lib::migrations::utils::value_to_type;
+use crate::sub_lib::neighborhood::HolePunch_0v1;
+use crate::sub_lib::versioned_data::Migrations;
+use crate::sub_lib::versioned_data::FUTURE_VERSION;
+use crate::sub_lib::versioned_data::{MigrationError, StepError, VersionedData};
+use lazy_static::lazy_static;
+use serde_cbor::Value;
+use std::convert::TryFrom;
+
+lazy_static! {
+    pub static ref MIGRATIONS: Migrations = {
+        let current_version = dv!(0, 1);
+        let mut migrations = Migrations::new(current_version);
+
+        migrate_value!(dv!(0, 1), HolePunch_0v1, HolePunchMF_0v1, {|value: serde_cbor::Value| {
+            HolePunch_0v1::try_from (&value)
+        }});
+        migrations.add_step (FUTURE_VERSION, dv!(0, 1), Box::new (HolePunchMF_0v1{}));
+
+        // add more steps here
+
+        migrations
+    };
+}
+
+impl Into<VersionedData<HolePunch_0v1>> for HolePunch_0v1 {
+    fn into(self) -> VersionedData<HolePunch_0v1> {
+        VersionedData::new(&MIGRATIONS, &self)
+    }
+}
+
+impl TryFrom<VersionedData<HolePunch_0v1>> for HolePunch_0v1 {
+    type Error = MigrationError;
+
+    fn try_from(vd: VersionedData<HolePunch_0v1>) -> Result<Self, Self::Error> {
+        vd.extract(&MIGRATIONS)
+    }
+}
+
+impl TryFrom<&Value> for HolePunch_0v1 {
+    type Error = StepError;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        match value_to_type::<HolePunch_0v1>(value) {
+            Some(hole_punch) => Ok(hole_punch),
+            None => Err(StepError::SemanticError(format!(
+                "Expected HolePunch_0v1; found {:?}",
+                value
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sub_lib::cryptde::PublicKey;
+    use std::net::SocketAddr;
+    use std::str::FromStr;
+
+    #[test]
+    fn can_migrate_from_the_present() {
+        let introduction = HolePunch_0v1::Introduction {
+            peer: PublicKey::new(&b"peer"[..]),
+            peer_addr: SocketAddr::from_str("1.2.3.4:5678").unwrap(),
+        };
+        let vd: VersionedData<HolePunch_0v1> = introduction.clone().into();
+        let serialized = serde_cbor::ser::to_vec(&vd).unwrap();
+        let deserialized =
+            serde_cbor::de::from_slice::<VersionedData<HolePunch_0v1>>(&serialized).unwrap();
+
+        let actual = HolePunch_0v1::try_from(deserialized).unwrap();
+
+        assert_eq!(actual, introduction);
+    }
+
+    #[test]
+    fn cannot_migrate_from_value_that_is_not_a_hole_punch() {
+        let value = Value::Bool(true);
+
+        let result = HolePunch_0v1::try_from(&value);
+
+        assert_eq!(
+            result,
+            Err(StepError::SemanticError(
+                "Expected HolePunch_0v1; found Bool(true)".to_string()
+            ))
+        )
+    }
+}
@@ -1,9 +1,15 @@
 // Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
 
+pub mod benchmark;
 pub mod client_request_payload;
 pub mod client_response_payload;
+pub mod client_udp_request_payload;
+pub mod client_udp_response_payload;
 pub mod dns_resolve_failure;
 pub mod gossip;
 pub mod gossip_failure;
+pub mod hole_punch;
 pub mod node_record_inner;
+pub mod payment_overdue;
+pub mod stream_ack_payload;
 pub mod utils;
@@ -1,16 +1,19 @@
 // Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
 
 use crate::neighborhood::node_record::NodeRecordInner_0v1;
-use crate::sub_lib::cryptde::PublicKey;
+use crate::sub_lib::attestation::Attestation;
+use crate::sub_lib::cryptde::{KeySuccession, PlainData, PublicKey};
+use crate::sub_lib::link_benchmark::LinkPerformance;
 use crate::sub_lib::migrations::utils::value_to_type;
 use crate::sub_lib::neighborhood::RatePack;
 use crate::sub_lib::versioned_data::{
     MigrationError, Migrations, StepError, VersionedData, FUTURE_VERSION,
 };
-use crate::sub_lib::wallet::Wallet;
+use crate::sub_lib::wallet::{Wallet, WalletChange};
 use lazy_static::lazy_static;
+use masq_lib::exit_policy::ExitPolicy;
 use serde_cbor::Value;
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::convert::TryFrom;
 
 lazy_static! {
@@ -57,12 +60,32 @@ impl TryFrom<&Value> for NodeRecordInner_0v1 {
                 let mut accepts_connections_opt: Option<bool> = None;
                 let mut routes_data_opt: Option<bool> = None;
                 let mut version_opt: Option<u32> = None;
+                let mut exit_policy_opt: Option<ExitPolicy> = None;
+                let mut key_succession_opt: Option<KeySuccession> = None;
+                let mut features_opt: Option<BTreeSet<String>> = None;
+                let mut kem_public_key_opt: Option<PlainData> = None;
+                let mut reputation_score_opt: Option<u8> = None;
+                let mut wallet_change_opt: Option<WalletChange> = None;
+                let mut link_performance_opt: Option<BTreeMap<PublicKey, LinkPerformance>> = None;
+                let mut attestations_opt: Option<Vec<Attestation>> = None;
+                let mut tombstone_opt: Option<bool> = None;
                 map.keys().for_each(|k| {
                     let v = map.get(k).expect("Disappeared");
                     match (k, v) {
                         (Value::Text(field_name), Value::Map(_)) => match field_name.as_str() {
                             "earning_wallet" => earning_wallet_opt = value_to_type::<Wallet>(v),
                             "rate_pack" => rate_pack_opt = value_to_type::<RatePack>(v),
+                            "exit_policy" => exit_policy_opt = value_to_type::<ExitPolicy>(v),
+                            "key_succession_opt" => {
+                                key_succession_opt = value_to_type::<KeySuccession>(v)
+                            }
+                            "wallet_change_opt" => {
+                                wallet_change_opt = value_to_type::<WalletChange>(v)
+                            }
+                            "link_performance" => {
+                                link_performance_opt =
+                                    value_to_type::<BTreeMap<PublicKey, LinkPerformance>>(v)
+                            }
                             _ => (),
                         },
                         (Value::Text(field_name), Value::Array(field_value)) => {
@@ -70,12 +93,19 @@ impl TryFrom<&Value> for NodeRecordInner_0v1 {
                                 "neighbors" => {
                                     neighbors_opt = Self::public_keys_to_btree_set(field_value)
                                 }
+                                "features" => features_opt = value_to_type::<BTreeSet<String>>(v),
+                                "attestations" => {
+                                    attestations_opt = value_to_type::<Vec<Attestation>>(v)
+                                }
                                 _ => (),
                             }
                         }
                         (Value::Text(field_name), Value::Bytes(field_value)) => {
                             match field_name.as_str() {
                                 "public_key" => public_key_opt = Some(PublicKey::new(field_value)),
+                                "kem_public_key_opt" => {
+                                    kem_public_key_opt = Some(PlainData::new(field_value))
+                                }
                                 _ => (),
                             }
                         }
@@ -85,6 +115,7 @@ impl TryFrom<&Value> for NodeRecordInner_0v1 {
                                     accepts_connections_opt = Some(*field_value)
                                 }
                                 "routes_data" => routes_data_opt = Some(*field_value),
+                                "tombstone" => tombstone_opt = Some(*field_value),
                                 _ => (),
                             }
                         }
@@ -95,6 +126,10 @@ impl TryFrom<&Value> for NodeRecordInner_0v1 {
                                     n if *n >= 0xFFFF_FFFFi128 => (),
                                     n => version_opt = Some(*n as u32),
                                 },
+                                "reputation_score_opt" => match field_value {
+                                    n if *n < 0 || *n > 100 => (),
+                                    n => reputation_score_opt = Some(*n as u8),
+                                },
                                 _ => (),
                             }
                         }
@@ -123,7 +158,10 @@ impl TryFrom<&Value> for NodeRecordInner_0v1 {
                 check_field(&mut missing_fields, "routes_data", &routes_data_opt);
                 check_field(&mut missing_fields, "version", &version_opt);
                 if !missing_fields.is_empty() {
-                    unimplemented!("{:?}", missing_fields.clone())
+                    return Err(StepError::SemanticError(format!(
+                        "Missing fields: {:?}",
+                        missing_fields
+                    )));
                 }
                 Ok(NodeRecordInner_0v1 {
                     public_key: public_key_opt.expect("public_key disappeared"),
@@ -133,6 +171,15 @@ impl TryFrom<&Value> for NodeRecordInner_0v1 {
                     accepts_connections: accepts_connections_opt.expect("public_key disappeared"),
                     routes_data: routes_data_opt.expect("public_key disappeared"),
                     version: version_opt.expect("public_key disappeared"),
+                    exit_policy: exit_policy_opt.unwrap_or_default(),
+                    key_succession_opt,
+                    features: features_opt.unwrap_or_default(),
+                    kem_public_key_opt,
+                    reputation_score_opt,
+                    wallet_change_opt,
+                    link_performance: link_performance_opt.unwrap_or_default(),
+                    attestations: attestations_opt.unwrap_or_default(),
+                    tombstone: tombstone_opt.unwrap_or_default(),
                 })
             }
             _ => Err(StepError::SemanticError(format!(
@@ -159,10 +206,12 @@ impl NodeRecordInner_0v1 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::sub_lib::cryptde::CryptData;
     use crate::sub_lib::neighborhood::DEFAULT_RATE_PACK;
     use crate::sub_lib::versioned_data::DataVersion;
     use serde_derive::{Deserialize, Serialize};
     use std::iter::FromIterator;
+    use std::str::FromStr;
 
     #[test]
     fn can_migrate_from_the_future() {
@@ -188,6 +237,15 @@ mod tests {
             accepts_connections: false,
             routes_data: true,
             version: 42,
+            exit_policy: ExitPolicy::default(),
+            key_succession_opt: None,
+            features: BTreeSet::new(),
+            kem_public_key_opt: None,
+            reputation_score_opt: None,
+            wallet_change_opt: None,
+            link_performance: BTreeMap::new(),
+            attestations: vec![],
+            tombstone: false,
         };
         let future_nri = ExampleFutureNRI {
             public_key: expected_nri.public_key.clone(),
@@ -211,6 +269,176 @@ mod tests {
         assert_eq!(actual_nri, expected_nri);
     }
 
+    #[test]
+    fn can_migrate_exit_policy_from_the_future() {
+        #[derive(Serialize, Deserialize)]
+        struct ExampleFutureNRI {
+            pub public_key: PublicKey,
+            pub earning_wallet: Wallet,
+            pub rate_pack: RatePack,
+            pub neighbors: BTreeSet<PublicKey>,
+            pub accepts_connections: bool,
+            pub routes_data: bool,
+            pub version: u32,
+            pub exit_policy: ExitPolicy,
+            pub another_field: String,
+        }
+        let expected_nri = NodeRecordInner_0v1 {
+            public_key: PublicKey::new(&[1, 2, 3, 4]),
+            earning_wallet: Wallet::new("0x0123456789012345678901234567890123456789"),
+            rate_pack: DEFAULT_RATE_PACK,
+            neighbors: BTreeSet::new(),
+            accepts_connections: true,
+            routes_data: true,
+            version: 42,
+            exit_policy: ExitPolicy::from_str("port:6881-6889,host:example.com,category:mail")
+                .unwrap(),
+            key_succession_opt: None,
+            features: BTreeSet::new(),
+            kem_public_key_opt: None,
+            reputation_score_opt: None,
+            wallet_change_opt: None,
+            link_performance: BTreeMap::new(),
+            attestations: vec![],
+            tombstone: false,
+        };
+        let future_nri = ExampleFutureNRI {
+            public_key: expected_nri.public_key.clone(),
+            earning_wallet: expected_nri.earning_wallet.clone(),
+            rate_pack: expected_nri.rate_pack.clone(),
+            neighbors: expected_nri.neighbors.clone(),
+            accepts_connections: expected_nri.accepts_connections,
+            routes_data: expected_nri.routes_data,
+            version: expected_nri.version,
+            exit_policy: expected_nri.exit_policy.clone(),
+            another_field: "These are the times that try men's souls".to_string(),
+        };
+        let future_migrations = Migrations::new(DataVersion::new(4095, 4095));
+        let serialized =
+            serde_cbor::ser::to_vec(&VersionedData::new(&future_migrations, &future_nri)).unwrap();
+        let future_vd =
+            serde_cbor::de::from_slice::<VersionedData<NodeRecordInner_0v1>>(&serialized).unwrap();
+
+        let actual_nri = NodeRecordInner_0v1::try_from(future_vd).unwrap();
+
+        assert_eq!(actual_nri, expected_nri);
+    }
+
+    #[test]
+    fn can_migrate_key_succession_features_and_kem_key_from_the_future() {
+        #[derive(Serialize, Deserialize)]
+        struct ExampleFutureNRI {
+            pub public_key: PublicKey,
+            pub earning_wallet: Wallet,
+            pub rate_pack: RatePack,
+            pub neighbors: BTreeSet<PublicKey>,
+            pub accepts_connections: bool,
+            pub routes_data: bool,
+            pub version: u32,
+            pub key_succession_opt: Option<KeySuccession>,
+            pub features: BTreeSet<String>,
+            pub kem_public_key_opt: Option<PlainData>,
+            pub another_field: String,
+        }
+        let expected_nri = NodeRecordInner_0v1 {
+            public_key: PublicKey::new(&[1, 2, 3, 4]),
+            earning_wallet: Wallet::new("0x0123456789012345678901234567890123456789"),
+            rate_pack: DEFAULT_RATE_PACK,
+            neighbors: BTreeSet::new(),
+            accepts_connections: true,
+            routes_data: true,
+            version: 42,
+            exit_policy: ExitPolicy::default(),
+            key_succession_opt: Some(KeySuccession {
+                previous_public_key: PublicKey::new(&[4, 3, 2, 1]),
+                endorsement: CryptData::new(&[5, 6, 7, 8]),
+            }),
+            features: vec!["cipher:rot13".to_string()].into_iter().collect(),
+            kem_public_key_opt: Some(PlainData::new(&[9, 10, 11, 12])),
+            reputation_score_opt: None,
+            wallet_change_opt: None,
+            link_performance: BTreeMap::new(),
+            attestations: vec![],
+            tombstone: false,
+        };
+        let future_nri = ExampleFutureNRI {
+            public_key: expected_nri.public_key.clone(),
+            earning_wallet: expected_nri.earning_wallet.clone(),
+            rate_pack: expected_nri.rate_pack.clone(),
+            neighbors: expected_nri.neighbors.clone(),
+            accepts_connections: expected_nri.accepts_connections,
+            routes_data: expected_nri.routes_data,
+            version: expected_nri.version,
+            key_succession_opt: expected_nri.key_succession_opt.clone(),
+            features: expected_nri.features.clone(),
+            kem_public_key_opt: expected_nri.kem_public_key_opt.clone(),
+            another_field: "These are the times that try men's souls".to_string(),
+        };
+        let future_migrations = Migrations::new(DataVersion::new(4095, 4095));
+        let serialized =
+            serde_cbor::ser::to_vec(&VersionedData::new(&future_migrations, &future_nri)).unwrap();
+        let future_vd =
+            serde_cbor::de::from_slice::<VersionedData<NodeRecordInner_0v1>>(&serialized).unwrap();
+
+        let actual_nri = NodeRecordInner_0v1::try_from(future_vd).unwrap();
+
+        assert_eq!(actual_nri, expected_nri);
+    }
+
+    #[test]
+    fn can_migrate_reputation_score_from_the_future() {
+        #[derive(Serialize, Deserialize)]
+        struct ExampleFutureNRI {
+            pub public_key: PublicKey,
+            pub earning_wallet: Wallet,
+            pub rate_pack: RatePack,
+            pub neighbors: BTreeSet<PublicKey>,
+            pub accepts_connections: bool,
+            pub routes_data: bool,
+            pub version: u32,
+            pub reputation_score_opt: Option<u8>,
+            pub another_field: String,
+        }
+        let expected_nri = NodeRecordInner_0v1 {
+            public_key: PublicKey::new(&[1, 2, 3, 4]),
+            earning_wallet: Wallet::new("0x0123456789012345678901234567890123456789"),
+            rate_pack: DEFAULT_RATE_PACK,
+            neighbors: BTreeSet::new(),
+            accepts_connections: true,
+            routes_data: true,
+            version: 42,
+            exit_policy: ExitPolicy::default(),
+            key_succession_opt: None,
+            features: BTreeSet::new(),
+            kem_public_key_opt: None,
+            reputation_score_opt: Some(66),
+            wallet_change_opt: None,
+            link_performance: BTreeMap::new(),
+            attestations: vec![],
+            tombstone: false,
+        };
+        let future_nri = ExampleFutureNRI {
+            public_key: expected_nri.public_key.clone(),
+            earning_wallet: expected_nri.earning_wallet.clone(),
+            rate_pack: expected_nri.rate_pack.clone(),
+            neighbors: expected_nri.neighbors.clone(),
+            accepts_connections: expected_nri.accepts_connections,
+            routes_data: expected_nri.routes_data,
+            version: expected_nri.version,
+            reputation_score_opt: expected_nri.reputation_score_opt,
+            another_field: "These are the times that try men's souls".to_string(),
+        };
+        let future_migrations = Migrations::new(DataVersion::new(4095, 4095));
+        let serialized =
+            serde_cbor::ser::to_vec(&VersionedData::new(&future_migrations, &future_nri)).unwrap();
+        let future_vd =
+            serde_cbor::de::from_slice::<VersionedData<NodeRecordInner_0v1>>(&serialized).unwrap();
+
+        let actual_nri = NodeRecordInner_0v1::try_from(future_vd).unwrap();
+
+        assert_eq!(actual_nri, expected_nri);
+    }
+
     #[test]
     fn cannot_migrate_from_value_other_than_map() {
         let value = Value::Bool(true);
@@ -224,4 +452,274 @@ mod tests {
             ))
         )
     }
+
+    #[test]
+    fn can_migrate_wallet_change_from_the_future() {
+        #[derive(Serialize, Deserialize)]
+        struct ExampleFutureNRI {
+            pub public_key: PublicKey,
+            pub earning_wallet: Wallet,
+            pub rate_pack: RatePack,
+            pub neighbors: BTreeSet<PublicKey>,
+            pub accepts_connections: bool,
+            pub routes_data: bool,
+            pub version: u32,
+            pub wallet_change_opt: Option<WalletChange>,
+            pub another_field: String,
+        }
+        let expected_nri = NodeRecordInner_0v1 {
+            public_key: PublicKey::new(&[1, 2, 3, 4]),
+            earning_wallet: Wallet::new("0x0123456789012345678901234567890123456789"),
+            rate_pack: DEFAULT_RATE_PACK,
+            neighbors: BTreeSet::new(),
+            accepts_connections: true,
+            routes_data: true,
+            version: 42,
+            exit_policy: ExitPolicy::default(),
+            key_succession_opt: None,
+            features: BTreeSet::new(),
+            kem_public_key_opt: None,
+            reputation_score_opt: None,
+            wallet_change_opt: Some(WalletChange {
+                previous_wallet: Wallet::new("0x9876543210987654321098765432109876543210"),
+                effective_block: 12345678,
+                signature: "0x05060708".to_string(),
+            }),
+            link_performance: BTreeMap::new(),
+            attestations: vec![],
+            tombstone: false,
+        };
+        let future_nri = ExampleFutureNRI {
+            public_key: expected_nri.public_key.clone(),
+            earning_wallet: expected_nri.earning_wallet.clone(),
+            rate_pack: expected_nri.rate_pack.clone(),
+            neighbors: expected_nri.neighbors.clone(),
+            accepts_connections: expected_nri.accepts_connections,
+            routes_data: expected_nri.routes_data,
+            version: expected_nri.version,
+            wallet_change_opt: expected_nri.wallet_change_opt.clone(),
+            another_field: "These are the times that try men's souls".to_string(),
+        };
+        let future_migrations = Migrations::new(DataVersion::new(4095, 4095));
+        let serialized =
+            serde_cbor::ser::to_vec(&VersionedData::new(&future_migrations, &future_nri)).unwrap();
+        let future_vd =
+            serde_cbor::de::from_slice::<VersionedData<NodeRecordInner_0v1>>(&serialized).unwrap();
+
+        let actual_nri = NodeRecordInner_0v1::try_from(future_vd).unwrap();
+
+        assert_eq!(actual_nri, expected_nri);
+    }
+    #[test]
+    fn can_migrate_link_performance_from_the_future() {
+        #[derive(Serialize, Deserialize)]
+        struct ExampleFutureNRI {
+            pub public_key: PublicKey,
+            pub earning_wallet: Wallet,
+            pub rate_pack: RatePack,
+            pub neighbors: BTreeSet<PublicKey>,
+            pub accepts_connections: bool,
+            pub routes_data: bool,
+            pub version: u32,
+            pub link_performance: BTreeMap<PublicKey, LinkPerformance>,
+            pub another_field: String,
+        }
+        let mut link_performance = BTreeMap::new();
+        link_performance.insert(
+            PublicKey::new(&[5, 6, 7, 8]),
+            LinkPerformance {
+                latency_ms: 40,
+                throughput_kbps: 1024,
+            },
+        );
+        let expected_nri = NodeRecordInner_0v1 {
+            public_key: PublicKey::new(&[1, 2, 3, 4]),
+            earning_wallet: Wallet::new("0x0123456789012345678901234567890123456789"),
+            rate_pack: DEFAULT_RATE_PACK,
+            neighbors: vec![PublicKey::new(&[5, 6, 7, 8])].into_iter().collect(),
+            accepts_connections: true,
+            routes_data: true,
+            version: 42,
+            exit_policy: ExitPolicy::default(),
+            key_succession_opt: None,
+            features: BTreeSet::new(),
+            kem_public_key_opt: None,
+            reputation_score_opt: None,
+            wallet_change_opt: None,
+            link_performance,
+            attestations: vec![],
+            tombstone: false,
+        };
+        let future_nri = ExampleFutureNRI {
+            public_key: expected_nri.public_key.clone(),
+            earning_wallet: expected_nri.earning_wallet.clone(),
+            rate_pack: expected_nri.rate_pack.clone(),
+            neighbors: expected_nri.neighbors.clone(),
+            accepts_connections: expected_nri.accepts_connections,
+            routes_data: expected_nri.routes_data,
+            version: expected_nri.version,
+            link_performance: expected_nri.link_performance.clone(),
+            another_field: "These are the times that try men's souls".to_string(),
+        };
+        let future_migrations = Migrations::new(DataVersion::new(4095, 4095));
+        let serialized =
+            serde_cbor::ser::to_vec(&VersionedData::new(&future_migrations, &future_nri)).unwrap();
+        let future_vd =
+            serde_cbor::de::from_slice::<VersionedData<NodeRecordInner_0v1>>(&serialized).unwrap();
+
+        let actual_nri = NodeRecordInner_0v1::try_from(future_vd).unwrap();
+
+        assert_eq!(actual_nri, expected_nri);
+    }
+
+    #[test]
+    fn can_migrate_tombstone_from_the_future() {
+        #[derive(Serialize, Deserialize)]
+        struct ExampleFutureNRI {
+            pub public_key: PublicKey,
+            pub earning_wallet: Wallet,
+            pub rate_pack: RatePack,
+            pub neighbors: BTreeSet<PublicKey>,
+            pub accepts_connections: bool,
+            pub routes_data: bool,
+            pub version: u32,
+            pub tombstone: bool,
+            pub another_field: String,
+        }
+        let expected_nri = NodeRecordInner_0v1 {
+            public_key: PublicKey::new(&[1, 2, 3, 4]),
+            earning_wallet: Wallet::new("0x0123456789012345678901234567890123456789"),
+            rate_pack: DEFAULT_RATE_PACK,
+            neighbors: BTreeSet::new(),
+            accepts_connections: true,
+            routes_data: true,
+            version: 42,
+            exit_policy: ExitPolicy::default(),
+            key_succession_opt: None,
+            features: BTreeSet::new(),
+            kem_public_key_opt: None,
+            reputation_score_opt: None,
+            wallet_change_opt: None,
+            link_performance: BTreeMap::new(),
+            attestations: vec![],
+            tombstone: true,
+        };
+        let future_nri = ExampleFutureNRI {
+            public_key: expected_nri.public_key.clone(),
+            earning_wallet: expected_nri.earning_wallet.clone(),
+            rate_pack: expected_nri.rate_pack.clone(),
+            neighbors: expected_nri.neighbors.clone(),
+            accepts_connections: expected_nri.accepts_connections,
+            routes_data: expected_nri.routes_data,
+            version: expected_nri.version,
+            tombstone: true,
+            another_field: "These are the times that try men's souls".to_string(),
+        };
+        let future_migrations = Migrations::new(DataVersion::new(4095, 4095));
+        let serialized =
+            serde_cbor::ser::to_vec(&VersionedData::new(&future_migrations, &future_nri)).unwrap();
+        let future_vd =
+            serde_cbor::de::from_slice::<VersionedData<NodeRecordInner_0v1>>(&serialized).unwrap();
+
+        let actual_nri = NodeRecordInner_0v1::try_from(future_vd).unwrap();
+
+        assert_eq!(actual_nri, expected_nri);
+    }
+
+    #[test]
+    fn can_migrate_attestations_from_the_future() {
+        #[derive(Serialize, Deserialize)]
+        struct ExampleFutureNRI {
+            pub public_key: PublicKey,
+            pub earning_wallet: Wallet,
+            pub rate_pack: RatePack,
+            pub neighbors: BTreeSet<PublicKey>,
+            pub accepts_connections: bool,
+            pub routes_data: bool,
+            pub version: u32,
+            pub attestations: Vec<Attestation>,
+            pub another_field: String,
+        }
+        let expected_nri = NodeRecordInner_0v1 {
+            public_key: PublicKey::new(&[1, 2, 3, 4]),
+            earning_wallet: Wallet::new("0x0123456789012345678901234567890123456789"),
+            rate_pack: DEFAULT_RATE_PACK,
+            neighbors: BTreeSet::new(),
+            accepts_connections: true,
+            routes_data: true,
+            version: 42,
+            exit_policy: ExitPolicy::default(),
+            key_succession_opt: None,
+            features: BTreeSet::new(),
+            kem_public_key_opt: None,
+            reputation_score_opt: None,
+            wallet_change_opt: None,
+            link_performance: BTreeMap::new(),
+            attestations: vec![Attestation {
+                subject: Wallet::new("0x9876543210987654321098765432109876543210"),
+                paid_on_time: 7,
+                overdue: 2,
+                signature: CryptData::new(&[5, 6, 7, 8]),
+            }],
+            tombstone: false,
+        };
+        let future_nri = ExampleFutureNRI {
+            public_key: expected_nri.public_key.clone(),
+            earning_wallet: expected_nri.earning_wallet.clone(),
+            rate_pack: expected_nri.rate_pack.clone(),
+            neighbors: expected_nri.neighbors.clone(),
+            accepts_connections: expected_nri.accepts_connections,
+            routes_data: expected_nri.routes_data,
+            version: expected_nri.version,
+            attestations: expected_nri.attestations.clone(),
+            another_field: "These are the times that try men's souls".to_string(),
+        };
+        let future_migrations = Migrations::new(DataVersion::new(4095, 4095));
+        let serialized =
+            serde_cbor::ser::to_vec(&VersionedData::new(&future_migrations, &future_nri)).unwrap();
+        let future_vd =
+            serde_cbor::de::from_slice::<VersionedData<NodeRecordInner_0v1>>(&serialized).unwrap();
+
+        let actual_nri = NodeRecordInner_0v1::try_from(future_vd).unwrap();
+
+        assert_eq!(actual_nri, expected_nri);
+    }
+
+    #[test]
+    fn truncated_records_from_the_future_are_errors() {
+        #[derive(Serialize, Deserialize)]
+        struct ExampleTruncatedNRI {
+            pub another_field: String,
+        }
+        let truncated = ExampleTruncatedNRI {
+            another_field: "These are the times that try men's souls".to_string(),
+        };
+        let future_migrations = Migrations::new(DataVersion::new(4095, 4095));
+        let short_vd = serde_cbor::de::from_slice::<VersionedData<NodeRecordInner_0v1>>(
+            &serde_cbor::ser::to_vec(&VersionedData::new(&future_migrations, &truncated)).unwrap(),
+        )
+        .unwrap();
+        let bytes = serde_cbor::ser::to_vec(&truncated).unwrap();
+        let cut_vd = VersionedData::<NodeRecordInner_0v1>::test_new(
+            DataVersion::new(4095, 4095),
+            bytes[..bytes.len() / 2].to_vec(),
+        );
+
+        assert_eq!(
+            NodeRecordInner_0v1::try_from(short_vd),
+            Err(MigrationError::MigrationFailed(StepError::SemanticError(
+                "Missing fields: [\"public_key\", \"earning_wallet\", \"rate_pack\", \"neighbors\", \"accepts_connections\", \"routes_data\", \"version\"]".to_string()
+            )))
+        );
+        assert_eq!(
+            NodeRecordInner_0v1::try_from(cut_vd),
+            Err(MigrationError::MigrationFailed(
+                StepError::DeserializationError(
+                    FUTURE_VERSION,
+                    DataVersion::new(0, 1),
+                    "Wampum".to_string()
+                )
+            ))
+        );
+    }
 }
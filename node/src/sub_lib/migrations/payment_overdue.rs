@@ -0,0 +1,93 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+
+use crate::sub_lib::accountant::PaymentOverdue_0v1;
+use crate::sub_lib::migrations::utils::value_to_type;
+use crate::sub_lib::versioned_data::Migrations;
+use crate::sub_lib::versioned_data::FUTURE_VERSION;
+use crate::sub_lib::versioned_data::{MigrationError, StepError, VersionedData};
+use lazy_static::lazy_static;
+use serde_cbor::Value;
+use std::convert::TryFrom;
+
+lazy_static! {
+    pub static ref MIGRATIONS: Migrations = {
+        let current_version = dv!(0, 1);
+        let mut migrations = Migrations::new(current_version);
+
+        migrate_value!(dv!(0, 1), PaymentOverdue_0v1, PaymentOverdueMF_0v1, {|value: serde_cbor::Value| {
+            PaymentOverdue_0v1::try_from (&value)
+        }});
+        migrations.add_step (FUTURE_VERSION, dv!(0, 1), Box::new (PaymentOverdueMF_0v1{}));
+
+        // add more steps here
+
+        migrations
+    };
+}
+
+impl Into<VersionedData<PaymentOverdue_0v1>> for PaymentOverdue_0v1 {
+    fn into(self) -> VersionedData<PaymentOverdue_0v1> {
+        VersionedData::new(&MIGRATIONS, &self)
+    }
+}
+
+impl TryFrom<VersionedData<PaymentOverdue_0v1>> for PaymentOverdue_0v1 {
+    type Error = MigrationError;
+
+    fn try_from(vd: VersionedData<PaymentOverdue_0v1>) -> Result<Self, Self::Error> {
+        vd.extract(&MIGRATIONS)
+    }
+}
+
+impl TryFrom<&Value> for PaymentOverdue_0v1 {
+    type Error = StepError;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        match value_to_type::<PaymentOverdue_0v1>(value) {
+            Some(notice) => Ok(notice),
+            None => Err(StepError::SemanticError(format!(
+                "Expected PaymentOverdue_0v1; found {:?}",
+                value
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::make_wallet;
+
+    #[test]
+    fn can_migrate_from_the_present() {
+        let notice = PaymentOverdue_0v1 {
+            creditor: make_wallet("creditor"),
+            debtor: make_wallet("debtor"),
+            balance: 1_234_567_890,
+            age_sec: 200_000,
+            ban_in_sec: 21_600,
+        };
+        let vd: VersionedData<PaymentOverdue_0v1> = notice.clone().into();
+        let serialized = serde_cbor::ser::to_vec(&vd).unwrap();
+        let deserialized =
+            serde_cbor::de::from_slice::<VersionedData<PaymentOverdue_0v1>>(&serialized).unwrap();
+
+        let actual = PaymentOverdue_0v1::try_from(deserialized).unwrap();
+
+        assert_eq!(actual, notice);
+    }
+
+    #[test]
+    fn cannot_migrate_from_value_that_is_not_a_payment_overdue_notice() {
+        let value = Value::Bool(true);
+
+        let result = PaymentOverdue_0v1::try_from(&value);
+
+        assert_eq!(
+            result,
+            Err(StepError::SemanticError(
+                "Expected PaymentOverdue_0v1; found Bool(true)".to_string()
+            ))
+        )
+    }
+}
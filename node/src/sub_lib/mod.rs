@@ -10,37 +10,65 @@ pub mod utils;
 pub mod versioned_data;
 
 pub mod accountant;
+pub mod attestation;
 pub mod bidi_hashmap;
 pub mod binary_traverser;
 pub mod blockchain_bridge;
+pub mod channel_windows;
 pub mod channel_wrappers;
+pub mod cipher_suite;
+pub mod cli_output;
+pub mod clock;
+pub mod clock_skew;
+pub mod cpu_affinity;
 pub mod cryptde;
 pub mod cryptde_null;
 pub mod cryptde_real;
+pub mod data_cap;
 pub mod data_version;
+pub mod descriptor_signature;
+pub mod deterministic;
+pub mod disk_overflow;
 pub mod dispatcher;
 pub mod framer;
 pub mod framer_utils;
+pub mod health;
+pub mod hole_punch;
 pub mod hop;
 pub mod hopper;
 pub mod http_packet_framer;
 pub mod http_response_start_finder;
+pub mod hybrid_kem;
+pub mod ip_reputation;
+pub mod key_rotation;
 pub mod limiter;
+pub mod link_benchmark;
+pub mod local_discovery;
+pub mod log_control;
+pub mod log_rotation;
 pub mod main_tools;
+pub mod metrics;
 pub mod migrations;
 pub mod neighborhood;
 pub mod node_addr;
+pub mod path_mtu;
+pub mod peer_table;
 pub mod peer_actors;
+pub mod port_conflict;
 pub mod proxy_client;
 pub mod proxy_server;
+pub mod raw_framer;
+pub mod redacted;
 pub mod route;
 pub mod sequence_buffer;
+pub mod sequence_window;
 pub mod sequencer;
 pub mod set_consuming_wallet_message;
 pub mod socket_server;
 pub mod stream_connector;
 pub mod stream_handler_pool;
 pub mod stream_key;
+pub mod stream_stats;
 pub mod tcp_wrappers;
 pub mod tls_framer;
 pub mod tokio_wrappers;
@@ -2,7 +2,7 @@
 use crate::blockchain::blockchain_interface::chain_id_from_name;
 use crate::neighborhood::gossip::Gossip_0v1;
 use crate::neighborhood::node_record::NodeRecord;
-use crate::sub_lib::cryptde::{CryptDE, PublicKey};
+use crate::sub_lib::cryptde::{CryptDE, CryptData, PlainData, PublicKey};
 use crate::sub_lib::dispatcher::{Component, StreamShutdownMsg};
 use crate::sub_lib::hopper::ExpiredCoresPackage;
 use crate::sub_lib::node_addr::NodeAddr;
@@ -21,7 +21,7 @@ use masq_lib::constants::DEFAULT_CHAIN_NAME;
 use masq_lib::ui_gateway::NodeFromUiMessage;
 use serde_derive::{Deserialize, Serialize};
 use std::fmt::{Debug, Display, Formatter};
-use std::net::IpAddr;
+use std::net::{IpAddr, SocketAddr};
 use std::str::FromStr;
 
 pub const DEFAULT_RATE_PACK: RatePack = RatePack {
@@ -247,6 +247,10 @@ pub struct NeighborhoodSubs {
     pub update_node_record_metadata: Recipient<NodeRecordMetadataMessage>,
     pub from_hopper: Recipient<ExpiredCoresPackage<Gossip_0v1>>,
     pub gossip_failure: Recipient<ExpiredCoresPackage<GossipFailure_0v1>>,
+    pub hole_punch: Recipient<ExpiredCoresPackage<HolePunch_0v1>>,
+    pub benchmark: Recipient<ExpiredCoresPackage<Benchmark_0v1>>,
+    pub hop_timings: Recipient<HopTimingsMessage>,
+    pub connection_audit: Recipient<ConnectionAuditMessage>,
     pub dispatcher_node_query: Recipient<DispatcherNodeQueryMessage>,
     pub remove_neighbor: Recipient<RemoveNeighborMessage>,
     pub stream_shutdown_sub: Recipient<StreamShutdownMsg>,
@@ -312,6 +316,26 @@ pub struct RouteQueryMessage {
     pub target_component: Component,
     pub minimum_hop_count: usize,
     pub return_component_opt: Option<Component>,
+    pub exit_target_opt: Option<ExitTarget>,
+    // Relay Nodes the route must not pass through, so that it's disjoint from another route
+    pub avoided_keys: Vec<PublicKey>,
+}
+
+// Where the data will go after it leaves the MASQ Network, so that routes can avoid exit Nodes
+// whose exit policies refuse it
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExitTarget {
+    pub hostname_opt: Option<String>,
+    pub port: u16,
+}
+
+impl fmt::Display for ExitTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match &self.hostname_opt {
+            Some(hostname) => write!(f, "{}:{}", hostname, self.port),
+            None => write!(f, "port {}", self.port),
+        }
+    }
 }
 
 impl Message for RouteQueryMessage {
@@ -325,8 +349,25 @@ impl RouteQueryMessage {
             target_component: Component::ProxyClient,
             minimum_hop_count,
             return_component_opt: Some(Component::ProxyServer),
+            exit_target_opt: None,
+            avoided_keys: vec![],
         }
     }
+
+    pub fn with_exit_target(mut self, hostname_opt: Option<String>, port: u16) -> Self {
+        self.exit_target_opt = Some(ExitTarget { hostname_opt, port });
+        self
+    }
+
+    pub fn with_target_key(mut self, target_key: PublicKey) -> Self {
+        self.target_key_opt = Some(target_key);
+        self
+    }
+
+    pub fn avoiding(mut self, avoided_keys: Vec<PublicKey>) -> Self {
+        self.avoided_keys = avoided_keys;
+        self
+    }
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -353,6 +394,58 @@ pub struct RemoveNeighborMessage {
     pub public_key: PublicKey,
 }
 
+// The encrypted HopTimings that came back on a package this Node originated, and when it arrived
+#[derive(Clone, Debug, Message, PartialEq)]
+pub struct HopTimingsMessage {
+    pub timings: Vec<CryptData>,
+    pub received_at_ms: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConnectionDirection {
+    Inbound,
+    Outbound,
+}
+
+impl Display for ConnectionDirection {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            ConnectionDirection::Inbound => write!(f, "inbound"),
+            ConnectionDirection::Outbound => write!(f, "outbound"),
+        }
+    }
+}
+
+impl FromStr for ConnectionDirection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "inbound" => Ok(ConnectionDirection::Inbound),
+            "outbound" => Ok(ConnectionDirection::Outbound),
+            _ => Err(format!("'{}' is not a connection direction", s)),
+        }
+    }
+}
+
+// Everything the connection audit log keeps about a clandestine connection once it has closed
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConnectionAuditRecord {
+    pub public_key_opt: Option<PublicKey>,
+    pub peer_addr: SocketAddr,
+    pub direction: ConnectionDirection,
+    pub opened_at: u64, // seconds since the epoch
+    pub duration_ms: u64,
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+    pub disconnect_reason: String,
+}
+
+#[derive(Clone, Debug, Message, PartialEq)]
+pub struct ConnectionAuditMessage {
+    pub record: ConnectionAuditRecord,
+}
+
 #[derive(Clone, Debug, Message, PartialEq)]
 pub enum NodeRecordMetadataMessage {
     Desirable(PublicKey, bool),
@@ -402,6 +495,52 @@ impl fmt::Display for GossipFailure_0v1 {
     }
 }
 
+// Two Nodes whose NATs won't let connections in can still connect directly if each connects to the
+// other at the same moment, so that each NAT sees the other's attempt as the answer to its own.
+// A Node connected to both of them arranges it, telling each one where the other will come from.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[allow(non_camel_case_types)]
+pub enum HolePunch_0v1 {
+    // To the rendezvous Node: tell target that I'm about to connect to it from this port
+    Request {
+        target: PublicKey,
+        port: u16,
+    },
+    // From the rendezvous Node: peer is about to connect to you from peer_addr
+    Introduction {
+        peer: PublicKey,
+        peer_addr: SocketAddr,
+    },
+}
+
+// Adjacent Nodes that both run link benchmarks time probes across the clandestine link between
+// them: an empty one for latency, then a padded one whose extra delay shows the bandwidth. A
+// speed test times the same pair of probes over a whole route to an exit that benchmarks.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[allow(non_camel_case_types)]
+pub enum Benchmark_0v1 {
+    // To the neighbor being measured: send back an Echo with this nonce as soon as you get this
+    Probe {
+        nonce: u64,
+        padding: PlainData,
+    },
+    // From the neighbor being measured: here's your Probe back, without its padding
+    Echo {
+        nonce: u64,
+    },
+    // To the exit of a speed test: send this back to originator along the rest of the route
+    RouteProbe {
+        nonce: u64,
+        originator: PublicKey,
+        padding: PlainData,
+    },
+    // From the exit of a speed test: here's your RouteProbe back, padding and all
+    RouteEcho {
+        nonce: u64,
+        padding: PlainData,
+    },
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -434,6 +573,10 @@ mod tests {
             update_node_record_metadata: recipient!(recorder, NodeRecordMetadataMessage),
             from_hopper: recipient!(recorder, ExpiredCoresPackage<Gossip_0v1>),
             gossip_failure: recipient!(recorder, ExpiredCoresPackage<GossipFailure_0v1>),
+            hole_punch: recipient!(recorder, ExpiredCoresPackage<HolePunch_0v1>),
+            benchmark: recipient!(recorder, ExpiredCoresPackage<Benchmark_0v1>),
+            hop_timings: recipient!(recorder, HopTimingsMessage),
+            connection_audit: recipient!(recorder, ConnectionAuditMessage),
             dispatcher_node_query: recipient!(recorder, DispatcherNodeQueryMessage),
             remove_neighbor: recipient!(recorder, RemoveNeighborMessage),
             stream_shutdown_sub: recipient!(recorder, StreamShutdownMsg),
@@ -619,10 +762,41 @@ mod tests {
                 target_component: Component::ProxyClient,
                 minimum_hop_count: 2,
                 return_component_opt: Some(Component::ProxyServer),
+                exit_target_opt: None,
+                avoided_keys: vec![],
             }
         );
     }
 
+    #[test]
+    fn route_request_with_exit_target() {
+        let result = RouteQueryMessage::data_indefinite_route_request(3)
+            .with_exit_target(Some("example.com".to_string()), 443);
+
+        assert_eq!(
+            result.exit_target_opt,
+            Some(ExitTarget {
+                hostname_opt: Some("example.com".to_string()),
+                port: 443,
+            })
+        );
+        assert_eq!(result.minimum_hop_count, 3);
+    }
+
+    #[test]
+    fn route_request_for_disjoint_route_to_known_exit() {
+        let result = RouteQueryMessage::data_indefinite_route_request(3)
+            .with_target_key(PublicKey::new(b"exit"))
+            .avoiding(vec![PublicKey::new(b"relay1"), PublicKey::new(b"relay2")]);
+
+        assert_eq!(result.target_key_opt, Some(PublicKey::new(b"exit")));
+        assert_eq!(
+            result.avoided_keys,
+            vec![PublicKey::new(b"relay1"), PublicKey::new(b"relay2")]
+        );
+        assert_eq!(result.exit_target_opt, None);
+    }
+
     #[test]
     fn standard_mode_results() {
         let one_neighbor = NodeDescriptor::from_str(main_cryptde(), "AQIDBA:1.2.3.4:1234").unwrap();
@@ -734,4 +908,20 @@ mod tests {
             assert_eq!(&gf.to_string(), expected_string);
         });
     }
+
+    #[test]
+    fn connection_directions_survive_a_round_trip_through_strings() {
+        vec![ConnectionDirection::Inbound, ConnectionDirection::Outbound]
+            .into_iter()
+            .for_each(|direction| {
+                assert_eq!(
+                    ConnectionDirection::from_str(&direction.to_string()),
+                    Ok(direction)
+                )
+            });
+        assert_eq!(
+            ConnectionDirection::from_str("sideways"),
+            Err("'sideways' is not a connection direction".to_string())
+        );
+    }
 }
@@ -0,0 +1,320 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use crate::sub_lib::logger::Logger;
+use crate::sub_lib::peer_table::MAX_PEER_TABLE_ENTRIES;
+use lazy_static::lazy_static;
+use net2::TcpBuilder;
+use std::collections::HashMap;
+use std::net::TcpStream as StdTcpStream;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Mutex;
+use tokio::net::TcpStream;
+
+// Plain Ethernet; most of the Internet carries packets this big
+pub const DEFAULT_PATH_MTU: usize = 1500;
+// What a neighbor whose connections keep dying in mid-transmission is moved down to, one step at a
+// time: PPPoE, typical VPN tunnels, the IPv6 minimum, and finally the IPv4 minimum.
+const FALLBACK_PATH_MTUS: [usize; 4] = [1492, 1400, 1280, 576];
+const IPV4_TCP_HEADER_BYTES: usize = 40;
+const IPV6_TCP_HEADER_BYTES: usize = 60;
+
+lazy_static! {
+    static ref PATH_MTUS: Mutex<PathMtuTable> = Mutex::new(PathMtuTable::new());
+}
+
+// The largest packet known to make it all the way to each neighbor's address. An MTU is a property
+// of the path rather than of any one connection, so what one connection learns, the next one uses.
+// A neighbor is forgotten when its record leaves the database; until then, a table that's full
+// learns nothing about new addresses, which just leaves them at the default.
+pub struct PathMtuTable {
+    mtus: HashMap<IpAddr, usize>,
+    limit: usize,
+}
+
+impl Default for PathMtuTable {
+    fn default() -> Self {
+        PathMtuTable {
+            mtus: HashMap::new(),
+            limit: MAX_PEER_TABLE_ENTRIES,
+        }
+    }
+}
+
+impl PathMtuTable {
+    pub fn new() -> PathMtuTable {
+        PathMtuTable::default()
+    }
+
+    pub fn mtu_for(&self, ip_addr: &IpAddr) -> usize {
+        *self.mtus.get(ip_addr).unwrap_or(&DEFAULT_PATH_MTU)
+    }
+
+    pub fn record_discovered(&mut self, ip_addr: IpAddr, mtu: usize) {
+        if mtu < self.mtu_for(&ip_addr) {
+            self.insert(ip_addr, mtu);
+        }
+    }
+
+    pub fn forget(&mut self, ip_addr: &IpAddr) {
+        self.mtus.remove(ip_addr);
+    }
+
+    fn insert(&mut self, ip_addr: IpAddr, mtu: usize) -> bool {
+        if self.mtus.len() >= self.limit && !self.mtus.contains_key(&ip_addr) {
+            return false;
+        }
+        self.mtus.insert(ip_addr, mtu);
+        true
+    }
+
+    // Packets too big for some link on the way, whose "fragmentation needed" complaints are being
+    // filtered, just vanish; so a timeout in mid-transmission earns the neighbor a smaller MTU.
+    // Returns the MTU to use from now on, or None if it was already as small as it gets.
+    pub fn record_black_hole(&mut self, ip_addr: IpAddr) -> Option<usize> {
+        let current = self.mtu_for(&ip_addr);
+        let smaller = *FALLBACK_PATH_MTUS.iter().find(|mtu| **mtu < current)?;
+        if self.insert(ip_addr, smaller) {
+            Some(smaller)
+        } else {
+            None
+        }
+    }
+}
+
+pub fn segment_size_for(mtu: usize, ip_addr: &IpAddr) -> usize {
+    match ip_addr {
+        IpAddr::V4(_) => mtu - IPV4_TCP_HEADER_BYTES,
+        IpAddr::V6(_) => mtu - IPV6_TCP_HEADER_BYTES,
+    }
+}
+
+// An unconnected socket for a clandestine connection to peer_addr, already limited to segments that
+// fit the path, so that the MSS we announce keeps the neighbor's segments small as well as ours.
+// None if the path is believed to carry full-sized packets, or if the socket can't be limited.
+pub fn socket_fitted_to_path(peer_addr: SocketAddr, logger: &Logger) -> Option<StdTcpStream> {
+    let peer_ip = peer_addr.ip();
+    let mtu = lock_path_mtus().mtu_for(&peer_ip);
+    if mtu >= DEFAULT_PATH_MTU {
+        return None;
+    }
+    let segment_size = segment_size_for(mtu, &peer_ip);
+    let result = match peer_ip {
+        IpAddr::V4(_) => TcpBuilder::new_v4(),
+        IpAddr::V6(_) => TcpBuilder::new_v6(),
+    }
+    .and_then(|builder| {
+        os::set_segment_size(&builder, segment_size)?;
+        builder.to_tcp_stream()
+    });
+    match result {
+        Ok(stream) => {
+            debug!(
+                logger,
+                "Limiting TCP segments to {} to {} bytes to fit a path MTU of {}",
+                peer_ip,
+                segment_size,
+                mtu
+            );
+            Some(stream)
+        }
+        Err(e) => {
+            warning!(
+                logger,
+                "Could not limit TCP segments to {} to {} bytes: {}",
+                peer_ip,
+                segment_size,
+                e
+            );
+            None
+        }
+    }
+}
+
+// Once a clandestine connection is up, the kernel may know more about the path than we do
+pub fn learn_path_mtu(stream: &TcpStream) {
+    if let (Ok(peer_addr), Some(mtu)) = (stream.peer_addr(), os::path_mtu(stream)) {
+        lock_path_mtus().record_discovered(peer_addr.ip(), mtu);
+    }
+}
+
+// Called when the neighbor at ip_addr leaves the database
+pub fn forget_path_mtu(ip_addr: &IpAddr) {
+    lock_path_mtus().forget(ip_addr);
+}
+
+pub fn report_black_hole(peer_addr: SocketAddr, logger: &Logger) {
+    if let Some(mtu) = lock_path_mtus().record_black_hole(peer_addr.ip()) {
+        warning!(
+            logger,
+            "Stream to {} timed out in mid-transmission; assuming a path MTU of {} from now on",
+            peer_addr,
+            mtu
+        );
+    }
+}
+
+fn lock_path_mtus() -> std::sync::MutexGuard<'static, PathMtuTable> {
+    PATH_MTUS.lock().expect("Path MTU table is poisoned")
+}
+
+#[cfg(target_os = "linux")]
+mod os {
+    use net2::TcpBuilder;
+    use nix::libc;
+    use std::io;
+    use std::mem;
+    use std::os::unix::io::AsRawFd;
+    use tokio::net::TcpStream;
+
+    pub fn set_segment_size(builder: &TcpBuilder, segment_size: usize) -> io::Result<()> {
+        let value = segment_size as libc::c_int;
+        let result = unsafe {
+            libc::setsockopt(
+                builder.as_raw_fd(),
+                libc::IPPROTO_TCP,
+                libc::TCP_MAXSEG,
+                &value as *const libc::c_int as *const libc::c_void,
+                mem::size_of::<libc::c_int>() as libc::socklen_t,
+            )
+        };
+        if result == 0 {
+            Ok(())
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    }
+
+    pub fn path_mtu(stream: &TcpStream) -> Option<usize> {
+        let (level, option) = match stream.peer_addr().ok()? {
+            std::net::SocketAddr::V4(_) => (libc::IPPROTO_IP, libc::IP_MTU),
+            std::net::SocketAddr::V6(_) => (libc::IPPROTO_IPV6, libc::IPV6_MTU),
+        };
+        let mut value: libc::c_int = 0;
+        let mut length = mem::size_of::<libc::c_int>() as libc::socklen_t;
+        let result = unsafe {
+            libc::getsockopt(
+                stream.as_raw_fd(),
+                level,
+                option,
+                &mut value as *mut libc::c_int as *mut libc::c_void,
+                &mut length,
+            )
+        };
+        if result == 0 && value > 0 {
+            Some(value as usize)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod os {
+    use net2::TcpBuilder;
+    use std::io;
+    use tokio::net::TcpStream;
+
+    pub fn set_segment_size(_builder: &TcpBuilder, _segment_size: usize) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            "not supported on this platform",
+        ))
+    }
+
+    pub fn path_mtu(_stream: &TcpStream) -> Option<usize> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn unknown_neighbors_get_the_default_mtu_and_discoveries_only_lower_it() {
+        let ip_addr = IpAddr::from_str("1.2.3.4").unwrap();
+        let mut subject = PathMtuTable::new();
+        let before = subject.mtu_for(&ip_addr);
+
+        subject.record_discovered(ip_addr, 1420);
+        subject.record_discovered(ip_addr, 9000);
+
+        assert_eq!(before, DEFAULT_PATH_MTU);
+        assert_eq!(subject.mtu_for(&ip_addr), 1420);
+        assert_eq!(
+            subject.mtu_for(&IpAddr::from_str("5.6.7.8").unwrap()),
+            DEFAULT_PATH_MTU
+        );
+    }
+
+    #[test]
+    fn black_holes_step_the_mtu_down_until_there_is_nowhere_left_to_go() {
+        let ip_addr = IpAddr::from_str("1.2.3.4").unwrap();
+        let mut subject = PathMtuTable::new();
+        subject.record_discovered(ip_addr, 1420);
+
+        let results = (0..4)
+            .map(|_| subject.record_black_hole(ip_addr))
+            .collect::<Vec<Option<usize>>>();
+
+        assert_eq!(results, vec![Some(1400), Some(1280), Some(576), None]);
+        assert_eq!(subject.mtu_for(&ip_addr), 576);
+    }
+
+    #[test]
+    fn a_full_table_learns_nothing_new_until_a_neighbor_is_forgotten() {
+        let one = IpAddr::from_str("1.1.1.1").unwrap();
+        let two = IpAddr::from_str("2.2.2.2").unwrap();
+        let three = IpAddr::from_str("3.3.3.3").unwrap();
+        let mut subject = PathMtuTable {
+            mtus: HashMap::new(),
+            limit: 2,
+        };
+        subject.record_discovered(one, 1420);
+        subject.record_discovered(two, 1420);
+
+        subject.record_discovered(three, 1280);
+        let black_hole_when_full = subject.record_black_hole(three);
+        let mtu_when_full = subject.mtu_for(&three);
+        subject.record_discovered(one, 1280);
+        subject.forget(&two);
+        subject.record_discovered(three, 1280);
+
+        assert_eq!(black_hole_when_full, None);
+        assert_eq!(mtu_when_full, DEFAULT_PATH_MTU);
+        assert_eq!(subject.mtu_for(&one), 1280);
+        assert_eq!(subject.mtu_for(&two), DEFAULT_PATH_MTU);
+        assert_eq!(subject.mtu_for(&three), 1280);
+    }
+
+    #[test]
+    fn segment_size_leaves_room_for_the_headers() {
+        assert_eq!(
+            segment_size_for(1492, &IpAddr::from_str("1.2.3.4").unwrap()),
+            1452
+        );
+        assert_eq!(
+            segment_size_for(1280, &IpAddr::from_str("::1").unwrap()),
+            1220
+        );
+    }
+
+    #[test]
+    fn paths_that_carry_full_sized_packets_need_no_special_socket() {
+        let peer_addr = SocketAddr::from_str("9.8.7.6:5432").unwrap();
+
+        let result = socket_fitted_to_path(peer_addr, &Logger::new("test"));
+
+        assert_eq!(result.is_none(), true);
+    }
+
+    #[test]
+    fn paths_known_to_be_small_get_a_socket_of_their_own_where_supported() {
+        let peer_addr = SocketAddr::from_str("3.4.5.6:7890").unwrap();
+        lock_path_mtus().record_discovered(peer_addr.ip(), 1280);
+
+        let result = socket_fitted_to_path(peer_addr, &Logger::new("test"));
+
+        assert_eq!(result.is_some(), cfg!(target_os = "linux"));
+    }
+}
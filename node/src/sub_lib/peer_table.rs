@@ -0,0 +1,90 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use crate::sub_lib::cryptde::PublicKey;
+use std::collections::HashMap;
+
+// Far more Nodes than a database should ever hold at once
+pub const MAX_PEER_TABLE_ENTRIES: usize = 4096;
+
+// What Gossip has told us about Nodes in the database, for code outside the Neighborhood that seals
+// data for them. The Neighborhood forgets a Node here when it drops the Node's record; the limit
+// keeps a flood of records from growing the table without end in the meantime.
+pub struct PeerTable<V> {
+    entries: HashMap<PublicKey, V>,
+    limit: usize,
+}
+
+impl<V> Default for PeerTable<V> {
+    fn default() -> Self {
+        PeerTable::new(MAX_PEER_TABLE_ENTRIES)
+    }
+}
+
+impl<V> PeerTable<V> {
+    pub fn new(limit: usize) -> PeerTable<V> {
+        PeerTable {
+            entries: HashMap::new(),
+            limit,
+        }
+    }
+
+    // False if the table is full and public_key isn't already in it
+    pub fn insert(&mut self, public_key: &PublicKey, value: V) -> bool {
+        if self.entries.len() >= self.limit && !self.entries.contains_key(public_key) {
+            return false;
+        }
+        self.entries.insert(public_key.clone(), value);
+        true
+    }
+
+    pub fn remove(&mut self, public_key: &PublicKey) {
+        self.entries.remove(public_key);
+    }
+
+    pub fn get(&self, public_key: &PublicKey) -> Option<&V> {
+        self.entries.get(public_key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_full_table_refuses_new_peers_but_still_updates_and_forgets_old_ones() {
+        let mut subject = PeerTable::new(2);
+        let one = PublicKey::new(b"one");
+        let two = PublicKey::new(b"two");
+        let three = PublicKey::new(b"three");
+        subject.insert(&one, 1);
+        subject.insert(&two, 2);
+
+        let refused = subject.insert(&three, 3);
+        let updated = subject.insert(&one, 11);
+        subject.remove(&two);
+        let accepted = subject.insert(&three, 3);
+
+        assert_eq!(refused, false);
+        assert_eq!(updated, true);
+        assert_eq!(accepted, true);
+        assert_eq!(subject.get(&one), Some(&11));
+        assert_eq!(subject.get(&two), None);
+        assert_eq!(subject.get(&three), Some(&3));
+        assert_eq!(subject.len(), 2);
+    }
+
+    #[test]
+    fn the_default_table_holds_as_many_peers_as_any_database_should() {
+        let subject: PeerTable<()> = PeerTable::default();
+
+        assert_eq!(subject.limit, MAX_PEER_TABLE_ENTRIES);
+        assert_eq!(subject.is_empty(), true);
+    }
+}
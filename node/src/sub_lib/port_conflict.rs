@@ -0,0 +1,84 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use crate::database::db_initializer::DbInitializerReal;
+use crate::proxy_server::process_identifier::port_holder;
+use std::io::ErrorKind;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener};
+
+// Looks at the ports the Node is about to bind before it binds them, so that a port some other
+// program already has can be reported plainly instead of as a panic halfway through startup
+pub trait PortChecker: Send {
+    // Who has the port, if anybody does; None if it looks free to the Node
+    fn holder(&self, port: u16) -> Option<String>;
+    fn free_clandestine_port(&self) -> u16;
+}
+
+pub struct PortCheckerReal {}
+
+impl PortChecker for PortCheckerReal {
+    // Only "address in use" counts: if the Node isn't allowed to bind the port, the real bind
+    // will say so itself
+    fn holder(&self, port: u16) -> Option<String> {
+        match TcpListener::bind(SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), port)) {
+            Err(ref e) if e.kind() == ErrorKind::AddrInUse => {
+                Some(port_holder(port).unwrap_or_else(|| "another program".to_string()))
+            }
+            _ => None,
+        }
+    }
+
+    fn free_clandestine_port(&self) -> u16 {
+        DbInitializerReal::choose_clandestine_port()
+    }
+}
+
+impl PortCheckerReal {
+    pub fn new() -> PortCheckerReal {
+        PortCheckerReal {}
+    }
+}
+
+impl Default for PortCheckerReal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub fn conflict_message(port: u16, holder: &str) -> String {
+    format!("Port {} is already in use by {}", port, holder)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_port_nobody_has_is_free() {
+        let port = TcpListener::bind("0.0.0.0:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+
+        let result = PortCheckerReal::new().holder(port);
+
+        assert_eq!(result, None);
+    }
+
+    #[test]
+    fn a_port_somebody_has_is_not_free() {
+        let listener = TcpListener::bind("0.0.0.0:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let result = PortCheckerReal::new().holder(port);
+
+        assert_eq!(result.is_some(), true);
+    }
+
+    #[test]
+    fn conflict_message_names_port_and_holder() {
+        assert_eq!(
+            conflict_message(5333, "nginx (process 1234)"),
+            "Port 5333 is already in use by nginx (process 1234)".to_string()
+        );
+    }
+}
@@ -1,18 +1,30 @@
 // Copyright (c) 2017-2019, Substratum LLC (https://substratum.net) and/or its affiliates. All rights reserved.
-use crate::sub_lib::cryptde::CryptDE;
+use crate::sub_lib::accountant::PaymentOverdue_0v1;
+use crate::sub_lib::cryptde::{CryptDE, PlainData};
 use crate::sub_lib::hopper::{ExpiredCoresPackage, MessageType};
 use crate::sub_lib::peer_actors::BindMessage;
-use crate::sub_lib::proxy_server::ClientRequestPayload_0v1;
+use crate::sub_lib::proxy_server::{
+    ClientRequestPayload_0v1, ClientUdpRequestPayload_0v1, StreamAckPayload_0v1,
+};
 use crate::sub_lib::sequence_buffer::SequencedPacket;
 use crate::sub_lib::stream_key::StreamKey;
 use crate::sub_lib::versioned_data::VersionedData;
 use actix::Message;
 use actix::Recipient;
+use masq_lib::exit_policy::ExitPolicy;
+use masq_lib::messages::UiStream;
+use masq_lib::secure_dns::SecureDnsServer;
 use serde_derive::{Deserialize, Serialize};
 use std::fmt::{Debug, Formatter};
 use std::net::Ipv4Addr;
 use std::net::SocketAddr;
 use std::net::SocketAddrV4;
+use std::time::Duration;
+
+// UDP has no end-of-stream marker, so exit sockets and their return routes are dropped after this long without traffic
+pub const UDP_IDLE_TIMEOUT: Duration = Duration::from_secs(120);
+pub const DEFAULT_DNS_CACHE_SIZE: usize = 1000;
+pub const DEFAULT_DNS_LOOKUP_THREADS: usize = 4;
 
 pub fn error_socket_addr() -> SocketAddr {
     SocketAddr::from(SocketAddrV4::new(Ipv4Addr::new(0, 0, 0, 0), 0))
@@ -22,6 +34,11 @@ pub fn error_socket_addr() -> SocketAddr {
 pub struct ProxyClientConfig {
     pub cryptde: &'static dyn CryptDE,
     pub dns_servers: Vec<SocketAddr>,
+    pub secure_dns_servers: Vec<SecureDnsServer>,
+    pub dns_cache_size: usize,
+    pub dns_lookup_threads: usize,
+    pub exit_policy: ExitPolicy,
+    pub stream_idle_timeout_opt: Option<Duration>,
     pub exit_service_rate: u64,
     pub exit_byte_rate: u64,
 }
@@ -33,6 +50,14 @@ pub struct ClientResponsePayload_0v1 {
     pub sequenced_packet: SequencedPacket,
 }
 
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[allow(non_camel_case_types)]
+pub struct ClientUdpResponsePayload_0v1 {
+    pub stream_key: StreamKey,
+    pub source: SocketAddr,
+    pub data: PlainData,
+}
+
 #[derive(Message, Clone, Debug, PartialEq, Deserialize, Serialize)]
 #[allow(non_camel_case_types)]
 pub struct DnsResolveFailure_0v1 {
@@ -54,6 +79,15 @@ impl Into<MessageType> for ClientResponsePayload_0v1 {
     }
 }
 
+impl Into<MessageType> for ClientUdpResponsePayload_0v1 {
+    fn into(self) -> MessageType {
+        MessageType::ClientUdpResponse(VersionedData::new(
+            &crate::sub_lib::migrations::client_udp_response_payload::MIGRATIONS,
+            &self,
+        ))
+    }
+}
+
 impl Into<MessageType> for DnsResolveFailure_0v1 {
     fn into(self) -> MessageType {
         MessageType::DnsResolveFailed(VersionedData::new(
@@ -69,6 +103,11 @@ pub struct ProxyClientSubs {
     pub from_hopper: Recipient<ExpiredCoresPackage<ClientRequestPayload_0v1>>,
     pub inbound_server_data: Recipient<InboundServerData>,
     pub dns_resolve_failed: Recipient<DnsResolveFailure_0v1>,
+    pub udp_from_hopper: Recipient<ExpiredCoresPackage<ClientUdpRequestPayload_0v1>>,
+    pub inbound_server_datagram: Recipient<InboundServerDatagram>,
+    pub stream_ack_from_hopper: Recipient<ExpiredCoresPackage<StreamAckPayload_0v1>>,
+    pub streams_query: Recipient<StreamsQuery>,
+    pub payment_overdue: Recipient<PaymentOverdue_0v1>,
 }
 
 impl Debug for ProxyClientSubs {
@@ -99,6 +138,22 @@ pub struct InboundServerData {
     pub data: Vec<u8>,
 }
 
+// A UI's streams request reaches the ProxyServer, which lists the streams it's consuming and
+// leaves the ProxyClient to add the ones it's serving and answer the UI
+#[derive(PartialEq, Clone, Message, Debug)]
+pub struct StreamsQuery {
+    pub client_id: u64,
+    pub context_id: u64,
+    pub consuming: Vec<UiStream>,
+}
+
+#[derive(PartialEq, Clone, Message, Debug)]
+pub struct InboundServerDatagram {
+    pub stream_key: StreamKey,
+    pub source: SocketAddr,
+    pub data: Vec<u8>,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -135,6 +190,11 @@ mod tests {
             from_hopper: recipient!(recorder, ExpiredCoresPackage<ClientRequestPayload_0v1>),
             inbound_server_data: recipient!(recorder, InboundServerData),
             dns_resolve_failed: recipient!(recorder, DnsResolveFailure_0v1),
+            udp_from_hopper: recipient!(recorder, ExpiredCoresPackage<ClientUdpRequestPayload_0v1>),
+            inbound_server_datagram: recipient!(recorder, InboundServerDatagram),
+            stream_ack_from_hopper: recipient!(recorder, ExpiredCoresPackage<StreamAckPayload_0v1>),
+            streams_query: recipient!(recorder, StreamsQuery),
+            payment_overdue: recipient!(recorder, PaymentOverdue_0v1),
         };
 
         assert_eq!(format!("{:?}", subject), "ProxyClientSubs");
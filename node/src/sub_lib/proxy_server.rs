@@ -1,20 +1,24 @@
 // Copyright (c) 2017-2019, Substratum LLC (https://substratum.net) and/or its affiliates. All rights reserved.
-use crate::sub_lib::cryptde::PublicKey;
+use crate::sub_lib::cryptde::{PlainData, PublicKey};
 use crate::sub_lib::data_version::DataVersion;
 use crate::sub_lib::dispatcher::InboundClientData;
 use crate::sub_lib::dispatcher::StreamShutdownMsg;
 use crate::sub_lib::hopper::{ExpiredCoresPackage, MessageType};
 use crate::sub_lib::neighborhood::{ExpectedService, RouteQueryResponse};
 use crate::sub_lib::peer_actors::BindMessage;
-use crate::sub_lib::proxy_client::{ClientResponsePayload_0v1, DnsResolveFailure_0v1};
+use crate::sub_lib::proxy_client::{
+    ClientResponsePayload_0v1, ClientUdpResponsePayload_0v1, DnsResolveFailure_0v1,
+};
 use crate::sub_lib::sequence_buffer::SequencedPacket;
 use crate::sub_lib::set_consuming_wallet_message::SetConsumingWalletMessage;
 use crate::sub_lib::stream_key::StreamKey;
 use crate::sub_lib::versioned_data::VersionedData;
 use actix::Message;
 use actix::Recipient;
+use masq_lib::ui_gateway::NodeFromUiMessage;
 use serde_derive::{Deserialize, Serialize};
 use std::fmt::Debug;
+use std::str::FromStr;
 
 pub const DEFAULT_MINIMUM_HOP_COUNT: usize = 3;
 
@@ -22,6 +26,91 @@ pub const DEFAULT_MINIMUM_HOP_COUNT: usize = 3;
 pub enum ProxyProtocol {
     HTTP,
     TLS,
+    SMTP,
+}
+
+// What a stream does with a second route to its exit Node, when the Neighborhood can find one
+// that shares no relay Nodes with the first. Striping alternates sequenced packets between the
+// two routes; duplicating sends every packet over both, and the exit Node drops the extra copies.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MultipathMode {
+    Off,
+    Stripe,
+    Duplicate,
+}
+
+impl Default for MultipathMode {
+    fn default() -> Self {
+        MultipathMode::Off
+    }
+}
+
+impl FromStr for MultipathMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "off" => Ok(MultipathMode::Off),
+            "stripe" => Ok(MultipathMode::Stripe),
+            "duplicate" => Ok(MultipathMode::Duplicate),
+            _ => Err(format!(
+                "Multipath mode '{}' is unknown: use off, stripe, or duplicate",
+                s
+            )),
+        }
+    }
+}
+
+// What to do with a TLS stream whose client hello hides the name of its server, so that there's
+// no telling which server the exit Node should connect to. Refusing sends the client an alert;
+// prompting holds the stream until a UI supplies a server name or refuses; a host name sends
+// every such stream there, which suits a client-facing server that can decrypt the real name.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EncryptedSniFallback {
+    Refuse,
+    Prompt,
+    Host(String),
+}
+
+impl Default for EncryptedSniFallback {
+    fn default() -> Self {
+        EncryptedSniFallback::Refuse
+    }
+}
+
+impl FromStr for EncryptedSniFallback {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "refuse" => Ok(EncryptedSniFallback::Refuse),
+            "prompt" => Ok(EncryptedSniFallback::Prompt),
+            host_name
+                if !host_name.is_empty()
+                    && host_name
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.') =>
+            {
+                Ok(EncryptedSniFallback::Host(host_name.to_string()))
+            }
+            _ => Err(format!(
+                "Encrypted-SNI fallback '{}' is unknown: use refuse, prompt, or a host name",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SocksCredentials {
+    pub username: String,
+    pub password: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SocksConfig {
+    pub port: u16,
+    pub credentials_opt: Option<SocksCredentials>,
 }
 
 // TODO: Based on the way it's used, this struct should comprise two elements: one, a nested
@@ -36,6 +125,10 @@ pub struct ClientRequestPayload_0v1 {
     pub target_port: u16,
     pub protocol: ProxyProtocol,
     pub originator_public_key: PublicKey,
+    // Set on the first request of a stream the originating Node picked up again after a restart:
+    // the exit Node answers over the new route alone, or says the stream is gone
+    #[serde(default)]
+    pub is_resumption: bool,
 }
 
 impl Into<MessageType> for ClientRequestPayload_0v1 {
@@ -53,6 +146,46 @@ impl ClientRequestPayload_0v1 {
     }
 }
 
+// One UDP datagram from a SOCKS5 client, bound for target_hostname:target_port. Datagrams are
+// neither sequenced nor retransmitted, so the exit Node sends them on as soon as they arrive.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[allow(non_camel_case_types)]
+pub struct ClientUdpRequestPayload_0v1 {
+    pub stream_key: StreamKey,
+    pub target_hostname: String,
+    pub target_port: u16,
+    pub data: PlainData,
+    pub originator_public_key: PublicKey,
+}
+
+impl Into<MessageType> for ClientUdpRequestPayload_0v1 {
+    fn into(self) -> MessageType {
+        MessageType::ClientUdpRequest(VersionedData::new(
+            &crate::sub_lib::migrations::client_udp_request_payload::MIGRATIONS,
+            &self,
+        ))
+    }
+}
+
+// Tells the exit Node which response packets on a stream have arrived, so that it can send more
+// and retransmit the ones that went missing
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[allow(non_camel_case_types)]
+pub struct StreamAckPayload_0v1 {
+    pub stream_key: StreamKey,
+    // Every packet before this one has arrived
+    pub next_sequence_number: u64,
+}
+
+impl Into<MessageType> for StreamAckPayload_0v1 {
+    fn into(self) -> MessageType {
+        MessageType::StreamAck(VersionedData::new(
+            &crate::sub_lib::migrations::stream_ack_payload::MIGRATIONS,
+            &self,
+        ))
+    }
+}
+
 #[derive(Message, Debug, PartialEq, Eq)]
 pub struct AddReturnRouteMessage {
     pub return_route_id: u32,
@@ -65,6 +198,8 @@ pub struct AddReturnRouteMessage {
 pub struct AddRouteMessage {
     pub stream_key: StreamKey,
     pub route: RouteQueryResponse,
+    // The stream already has a route; this is the disjoint one for multipath streaming
+    pub is_secondary: bool,
 }
 
 #[derive(Clone)]
@@ -74,10 +209,12 @@ pub struct ProxyServerSubs {
     pub from_dispatcher: Recipient<InboundClientData>,
     pub from_hopper: Recipient<ExpiredCoresPackage<ClientResponsePayload_0v1>>,
     pub dns_failure_from_hopper: Recipient<ExpiredCoresPackage<DnsResolveFailure_0v1>>,
+    pub udp_from_hopper: Recipient<ExpiredCoresPackage<ClientUdpResponsePayload_0v1>>,
     pub add_return_route: Recipient<AddReturnRouteMessage>,
     pub add_route: Recipient<AddRouteMessage>,
     pub stream_shutdown_sub: Recipient<StreamShutdownMsg>,
     pub set_consuming_wallet_sub: Recipient<SetConsumingWalletMessage>,
+    pub from_ui_message_sub: Recipient<NodeFromUiMessage>,
 }
 
 impl Debug for ProxyServerSubs {
@@ -105,12 +242,61 @@ mod tests {
                 recorder,
                 ExpiredCoresPackage<DnsResolveFailure_0v1>
             ),
+            udp_from_hopper: recipient!(
+                recorder,
+                ExpiredCoresPackage<ClientUdpResponsePayload_0v1>
+            ),
             add_return_route: recipient!(recorder, AddReturnRouteMessage),
             add_route: recipient!(recorder, AddRouteMessage),
             stream_shutdown_sub: recipient!(recorder, StreamShutdownMsg),
             set_consuming_wallet_sub: recipient!(recorder, SetConsumingWalletMessage),
+            from_ui_message_sub: recipient!(recorder, NodeFromUiMessage),
         };
 
         assert_eq!(format!("{:?}", subject), "ProxyServerSubs");
     }
+
+    #[test]
+    fn multipath_mode_is_parsed_without_regard_to_case() {
+        assert_eq!(MultipathMode::from_str("off"), Ok(MultipathMode::Off));
+        assert_eq!(MultipathMode::from_str("Stripe"), Ok(MultipathMode::Stripe));
+        assert_eq!(
+            MultipathMode::from_str("DUPLICATE"),
+            Ok(MultipathMode::Duplicate)
+        );
+        assert_eq!(
+            MultipathMode::from_str("booga"),
+            Err("Multipath mode 'booga' is unknown: use off, stripe, or duplicate".to_string())
+        );
+        assert_eq!(MultipathMode::default(), MultipathMode::Off);
+    }
+
+    #[test]
+    fn encrypted_sni_fallback_is_parsed_without_regard_to_case() {
+        assert_eq!(
+            EncryptedSniFallback::from_str("Refuse"),
+            Ok(EncryptedSniFallback::Refuse)
+        );
+        assert_eq!(
+            EncryptedSniFallback::from_str("PROMPT"),
+            Ok(EncryptedSniFallback::Prompt)
+        );
+        assert_eq!(
+            EncryptedSniFallback::from_str("Crypto.Cloudflare.com"),
+            Ok(EncryptedSniFallback::Host(
+                "crypto.cloudflare.com".to_string()
+            ))
+        );
+        assert_eq!(
+            EncryptedSniFallback::from_str("booga booga"),
+            Err(
+                "Encrypted-SNI fallback 'booga booga' is unknown: use refuse, prompt, or a host name"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            EncryptedSniFallback::default(),
+            EncryptedSniFallback::Refuse
+        );
+    }
 }
@@ -0,0 +1,67 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use crate::sub_lib::framer::FramedChunk;
+use crate::sub_lib::framer::Framer;
+
+// Imposes no framing at all: whatever data has arrived so far is handed on as a single chunk.
+// Useful for protocols such as SOCKS, where the framing is whatever the application inside
+// the tunnel uses.
+#[derive(Default)]
+pub struct RawFramer {
+    data_so_far: Vec<u8>,
+}
+
+impl Framer for RawFramer {
+    fn add_data(&mut self, data: &[u8]) {
+        self.data_so_far.extend(data);
+    }
+
+    fn take_frame(&mut self) -> Option<FramedChunk> {
+        if self.data_so_far.is_empty() {
+            None
+        } else {
+            Some(FramedChunk {
+                chunk: self.data_so_far.split_off(0),
+                last_chunk: false,
+            })
+        }
+    }
+}
+
+impl RawFramer {
+    pub fn new() -> RawFramer {
+        Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn take_frame_returns_none_when_no_data_has_arrived() {
+        let mut subject = RawFramer::new();
+
+        let result = subject.take_frame();
+
+        assert_eq!(result, None);
+    }
+
+    #[test]
+    fn take_frame_returns_everything_that_has_arrived() {
+        let mut subject = RawFramer::new();
+        subject.add_data(&[1, 2, 3]);
+        subject.add_data(&[4, 5]);
+
+        let first_result = subject.take_frame();
+        let second_result = subject.take_frame();
+
+        assert_eq!(
+            first_result,
+            Some(FramedChunk {
+                chunk: vec![1, 2, 3, 4, 5],
+                last_chunk: false,
+            })
+        );
+        assert_eq!(second_result, None);
+    }
+}
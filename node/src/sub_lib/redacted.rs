@@ -0,0 +1,99 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+#[cfg(test)]
+use std::cell::Cell;
+use std::fmt;
+use std::fmt::{Debug, Display, Formatter};
+#[cfg(not(test))]
+use std::sync::atomic::{AtomicBool, Ordering};
+
+pub const REDACTION_MARK: &str = "<redacted>";
+
+// On unless the Node is started with --log-redaction off, which is for developers who have to see
+// what's in their own logs
+#[cfg(not(test))]
+static REDACTION_ENABLED: AtomicBool = AtomicBool::new(true);
+
+// Tests run in parallel on many threads, and most of them expect to see real values in the log,
+// so each thread has its own switch, and it starts out off
+#[cfg(test)]
+thread_local! {
+    static REDACTION_ENABLED: Cell<bool> = Cell::new(false);
+}
+
+#[cfg(not(test))]
+pub fn set_redaction(enabled: bool) {
+    REDACTION_ENABLED.store(enabled, Ordering::Relaxed)
+}
+
+#[cfg(test)]
+pub fn set_redaction(enabled: bool) {
+    REDACTION_ENABLED.with(|cell| cell.set(enabled))
+}
+
+#[cfg(not(test))]
+pub fn redaction_enabled() -> bool {
+    REDACTION_ENABLED.load(Ordering::Relaxed)
+}
+
+#[cfg(test)]
+pub fn redaction_enabled() -> bool {
+    REDACTION_ENABLED.with(|cell| cell.get())
+}
+
+// Wrap anything that could identify a user or unlock their money (wallet addresses, node
+// descriptors and IP addresses, passwords, seeds and derivation paths) in this before it goes into
+// a log message or a panic, and it will be shown as <redacted> instead
+pub struct Redacted<T>(pub T);
+
+impl<T: Display> Display for Redacted<T> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        if redaction_enabled() {
+            f.write_str(REDACTION_MARK)
+        } else {
+            Display::fmt(&self.0, f)
+        }
+    }
+}
+
+impl<T: Debug> Debug for Redacted<T> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        if redaction_enabled() {
+            f.write_str(REDACTION_MARK)
+        } else {
+            Debug::fmt(&self.0, f)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redacted_values_are_hidden_when_redaction_is_on() {
+        set_redaction(true);
+
+        let display = format!("{}", Redacted("0x0123456789012345678901234567890123456789"));
+        let debug = format!("{:?}", Redacted(Some("m/44'/60'/0'/0/0")));
+
+        set_redaction(false);
+        assert_eq!(display, "<redacted>".to_string());
+        assert_eq!(debug, "<redacted>".to_string());
+    }
+
+    #[test]
+    fn redacted_values_are_shown_when_redaction_is_off() {
+        set_redaction(false);
+
+        let display = format!("{}", Redacted("1.2.3.4"));
+        let debug = format!("{:?}", Redacted(Some("1.2.3.4")));
+
+        assert_eq!(display, "1.2.3.4".to_string());
+        assert_eq!(debug, "Some(\"1.2.3.4\")".to_string());
+    }
+
+    #[test]
+    fn redaction_is_off_for_tests_unless_they_turn_it_on() {
+        assert_eq!(redaction_enabled(), false);
+    }
+}
@@ -13,7 +13,7 @@ use std::cmp::min;
 use std::iter;
 use web3::types::Address;
 
-#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub struct Route {
     pub hops: Vec<CryptData>,
 }
@@ -70,6 +70,28 @@ impl Route {
         )
     }
 
+    // Like round_trip, but asks the exit and every relay on the way back to stamp the package
+    // with the time it left them, encrypted to timing_key
+    pub fn timed_round_trip(
+        route_segment_over: RouteSegment,
+        route_segment_back: RouteSegment,
+        cryptde: &dyn CryptDE, // Must be the CryptDE of the originating Node: used to encrypt return_route_id.
+        consuming_wallet: Option<Wallet>,
+        return_route_id: u32,
+        contract_address: Option<Address>,
+        timing_key: &PublicKey,
+    ) -> Result<Route, CodexError> {
+        Self::construct_with_timing(
+            route_segment_over,
+            Some(route_segment_back),
+            cryptde,
+            consuming_wallet,
+            Some(return_route_id),
+            contract_address,
+            Some(timing_key),
+        )
+    }
+
     pub fn id(&self, cryptde: &dyn CryptDE) -> Result<u32, String> {
         if let Some(first) = self.hops.first() {
             match decodex(cryptde, first) {
@@ -148,6 +170,26 @@ impl Route {
         consuming_wallet: Option<Wallet>,
         return_route_id_opt: Option<u32>,
         contract_address: Option<Address>,
+    ) -> Result<Route, CodexError> {
+        Self::construct_with_timing(
+            over,
+            back,
+            cryptde,
+            consuming_wallet,
+            return_route_id_opt,
+            contract_address,
+            None,
+        )
+    }
+
+    fn construct_with_timing(
+        over: RouteSegment,
+        back: Option<RouteSegment>,
+        cryptde: &dyn CryptDE,
+        consuming_wallet: Option<Wallet>,
+        return_route_id_opt: Option<u32>,
+        contract_address: Option<Address>,
+        timing_key_opt: Option<&PublicKey>,
     ) -> Result<Route, CodexError> {
         if let Some(error) = Route::validate_route_segments(&over, &back) {
             return Err(CodexError::RoutingError(error));
@@ -169,6 +211,7 @@ impl Route {
             over_component,
             &mut hops,
             contract_address,
+            timing_key_opt,
         );
 
         Route::hops_to_route(
@@ -230,6 +273,7 @@ impl Route {
         over_component: Component,
         hops: &mut Vec<LiveHop>,
         contract_address: Option<Address>,
+        timing_key_opt: Option<&PublicKey>,
     ) {
         if let Some(back) = back_option {
             let back_component = back.recipient;
@@ -242,7 +286,7 @@ impl Route {
                     Component::Hopper
                 };
 
-                hops.push(LiveHop::new(
+                let hop = LiveHop::new(
                     next_key,
                     consuming_wallet.clone().map(|w| {
                         w.as_payer(
@@ -251,7 +295,11 @@ impl Route {
                         )
                     }),
                     component,
-                ))
+                );
+                hops.push(match timing_key_opt {
+                    Some(timing_key) => hop.with_timing_key(timing_key),
+                    None => hop,
+                })
             }
             let next_key = PublicKey::new(b"");
             match back_keys.last() {
@@ -318,7 +366,7 @@ impl Route {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct RouteSegment {
     pub keys: Vec<PublicKey>,
     pub recipient: Component,
@@ -455,6 +503,40 @@ mod tests {
         );
     }
 
+    #[test]
+    fn timed_round_trip_asks_only_the_nodes_on_the_way_back_for_timing() {
+        let a_key = PublicKey::new(&[65, 65, 65]);
+        let b_key = PublicKey::new(&[66, 66, 66]);
+        let c_key = PublicKey::new(&[67, 67, 67]);
+        let timing_key = PublicKey::new(&[84, 84, 84]);
+        let cryptde = main_cryptde();
+
+        let subject = Route::timed_round_trip(
+            RouteSegment::new(vec![&a_key, &b_key, &c_key], Component::ProxyClient),
+            RouteSegment::new(vec![&c_key, &b_key, &a_key], Component::ProxyServer),
+            cryptde,
+            None,
+            1234,
+            None,
+            &timing_key,
+        )
+        .unwrap();
+
+        let timing_keys = [&a_key, &b_key, &c_key, &b_key, &a_key]
+            .iter()
+            .zip(subject.hops.iter())
+            .map(|(key, hop)| {
+                LiveHop::decode(&CryptDENull::from(key, DEFAULT_CHAIN_ID), hop)
+                    .unwrap()
+                    .timing_key_opt
+            })
+            .collect::<Vec<Option<PublicKey>>>();
+        assert_eq!(
+            timing_keys,
+            vec![None, None, Some(timing_key.clone()), Some(timing_key), None]
+        );
+    }
+
     #[test]
     fn construct_can_make_long_multistop_route() {
         let a_key = PublicKey::new(&[65, 65, 65]);
@@ -783,9 +865,9 @@ mod tests {
             result,
             String::from(
                 r#"
-Encrypted with AQIDBA: LiveHop { public_key: AgMEBQ, payer: Some(Payer { wallet: Wallet { kind: Address(0x71d0fc7d1c570b1ed786382b551a09391c91e33d) }, proof: Signature { v: 0, r: "8b663e5a10f40c3307e6fb5340482a5e11df78dafc619ceff97f11fa79fea320", s: "8b663e5a10f40c3307e6fb5340482a5e11df78dafc619ceff97f11fa79fea320" } }), component: Hopper }
-Encrypted with AgMEBQ: LiveHop { public_key: AwQFBg, payer: Some(Payer { wallet: Wallet { kind: Address(0x71d0fc7d1c570b1ed786382b551a09391c91e33d) }, proof: Signature { v: 1, r: "63be72962f19dda7802220ed48c0d8199d510b45608a3789c50f61912b98a15b", s: "63be72962f19dda7802220ed48c0d8199d510b45608a3789c50f61912b98a15b" } }), component: Hopper }
-Encrypted with AwQFBg: LiveHop { public_key: , payer: Some(Payer { wallet: Wallet { kind: Address(0x71d0fc7d1c570b1ed786382b551a09391c91e33d) }, proof: Signature { v: 1, r: "9ca23557adf96d7aed407a06ce96851a4184e947a7b29b6c3872eef902fcba1e", s: "9ca23557adf96d7aed407a06ce96851a4184e947a7b29b6c3872eef902fcba1e" } }), component: Neighborhood }
+Encrypted with AQIDBA: LiveHop { public_key: AgMEBQ, payer: Some(Payer { wallet: Wallet { kind: Address(0x71d0fc7d1c570b1ed786382b551a09391c91e33d) }, proof: Signature { v: 0, r: "8b663e5a10f40c3307e6fb5340482a5e11df78dafc619ceff97f11fa79fea320", s: "8b663e5a10f40c3307e6fb5340482a5e11df78dafc619ceff97f11fa79fea320" } }), component: Hopper, timing_key_opt: None }
+Encrypted with AgMEBQ: LiveHop { public_key: AwQFBg, payer: Some(Payer { wallet: Wallet { kind: Address(0x71d0fc7d1c570b1ed786382b551a09391c91e33d) }, proof: Signature { v: 1, r: "63be72962f19dda7802220ed48c0d8199d510b45608a3789c50f61912b98a15b", s: "63be72962f19dda7802220ed48c0d8199d510b45608a3789c50f61912b98a15b" } }), component: Hopper, timing_key_opt: None }
+Encrypted with AwQFBg: LiveHop { public_key: , payer: Some(Payer { wallet: Wallet { kind: Address(0x71d0fc7d1c570b1ed786382b551a09391c91e33d) }, proof: Signature { v: 1, r: "9ca23557adf96d7aed407a06ce96851a4184e947a7b29b6c3872eef902fcba1e", s: "9ca23557adf96d7aed407a06ce96851a4184e947a7b29b6c3872eef902fcba1e" } }), component: Neighborhood, timing_key_opt: None }
 "#
             )
         );
@@ -820,11 +902,11 @@ Encrypted with AwQFBg: LiveHop { public_key: , payer: Some(Payer { wallet: Walle
             result,
             String::from(
                 r#"
-Encrypted with AQIDBA: LiveHop { public_key: AgMEBQ, payer: Some(Payer { wallet: Wallet { kind: Address(0x71d0fc7d1c570b1ed786382b551a09391c91e33d) }, proof: Signature { v: 0, r: "8b663e5a10f40c3307e6fb5340482a5e11df78dafc619ceff97f11fa79fea320", s: "8b663e5a10f40c3307e6fb5340482a5e11df78dafc619ceff97f11fa79fea320" } }), component: Hopper }
-Encrypted with AgMEBQ: LiveHop { public_key: AwQFBg, payer: Some(Payer { wallet: Wallet { kind: Address(0x71d0fc7d1c570b1ed786382b551a09391c91e33d) }, proof: Signature { v: 1, r: "63be72962f19dda7802220ed48c0d8199d510b45608a3789c50f61912b98a15b", s: "63be72962f19dda7802220ed48c0d8199d510b45608a3789c50f61912b98a15b" } }), component: Hopper }
-Encrypted with AwQFBg: LiveHop { public_key: AgMEBQ, payer: Some(Payer { wallet: Wallet { kind: Address(0x71d0fc7d1c570b1ed786382b551a09391c91e33d) }, proof: Signature { v: 1, r: "9ca23557adf96d7aed407a06ce96851a4184e947a7b29b6c3872eef902fcba1e", s: "9ca23557adf96d7aed407a06ce96851a4184e947a7b29b6c3872eef902fcba1e" } }), component: ProxyClient }
-Encrypted with AgMEBQ: LiveHop { public_key: AQIDBA, payer: Some(Payer { wallet: Wallet { kind: Address(0x71d0fc7d1c570b1ed786382b551a09391c91e33d) }, proof: Signature { v: 1, r: "63be72962f19dda7802220ed48c0d8199d510b45608a3789c50f61912b98a15b", s: "63be72962f19dda7802220ed48c0d8199d510b45608a3789c50f61912b98a15b" } }), component: Hopper }
-Encrypted with AQIDBA: LiveHop { public_key: , payer: Some(Payer { wallet: Wallet { kind: Address(0x71d0fc7d1c570b1ed786382b551a09391c91e33d) }, proof: Signature { v: 0, r: "8b663e5a10f40c3307e6fb5340482a5e11df78dafc619ceff97f11fa79fea320", s: "8b663e5a10f40c3307e6fb5340482a5e11df78dafc619ceff97f11fa79fea320" } }), component: ProxyServer }
+Encrypted with AQIDBA: LiveHop { public_key: AgMEBQ, payer: Some(Payer { wallet: Wallet { kind: Address(0x71d0fc7d1c570b1ed786382b551a09391c91e33d) }, proof: Signature { v: 0, r: "8b663e5a10f40c3307e6fb5340482a5e11df78dafc619ceff97f11fa79fea320", s: "8b663e5a10f40c3307e6fb5340482a5e11df78dafc619ceff97f11fa79fea320" } }), component: Hopper, timing_key_opt: None }
+Encrypted with AgMEBQ: LiveHop { public_key: AwQFBg, payer: Some(Payer { wallet: Wallet { kind: Address(0x71d0fc7d1c570b1ed786382b551a09391c91e33d) }, proof: Signature { v: 1, r: "63be72962f19dda7802220ed48c0d8199d510b45608a3789c50f61912b98a15b", s: "63be72962f19dda7802220ed48c0d8199d510b45608a3789c50f61912b98a15b" } }), component: Hopper, timing_key_opt: None }
+Encrypted with AwQFBg: LiveHop { public_key: AgMEBQ, payer: Some(Payer { wallet: Wallet { kind: Address(0x71d0fc7d1c570b1ed786382b551a09391c91e33d) }, proof: Signature { v: 1, r: "9ca23557adf96d7aed407a06ce96851a4184e947a7b29b6c3872eef902fcba1e", s: "9ca23557adf96d7aed407a06ce96851a4184e947a7b29b6c3872eef902fcba1e" } }), component: ProxyClient, timing_key_opt: None }
+Encrypted with AgMEBQ: LiveHop { public_key: AQIDBA, payer: Some(Payer { wallet: Wallet { kind: Address(0x71d0fc7d1c570b1ed786382b551a09391c91e33d) }, proof: Signature { v: 1, r: "63be72962f19dda7802220ed48c0d8199d510b45608a3789c50f61912b98a15b", s: "63be72962f19dda7802220ed48c0d8199d510b45608a3789c50f61912b98a15b" } }), component: Hopper, timing_key_opt: None }
+Encrypted with AQIDBA: LiveHop { public_key: , payer: Some(Payer { wallet: Wallet { kind: Address(0x71d0fc7d1c570b1ed786382b551a09391c91e33d) }, proof: Signature { v: 0, r: "8b663e5a10f40c3307e6fb5340482a5e11df78dafc619ceff97f11fa79fea320", s: "8b663e5a10f40c3307e6fb5340482a5e11df78dafc619ceff97f11fa79fea320" } }), component: ProxyServer, timing_key_opt: None }
 Encrypted with AQIDBA: Return Route ID: 1234
 "#
             )
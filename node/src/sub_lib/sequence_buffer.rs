@@ -254,6 +254,7 @@ mod tests {
             last_data: true,
             data: vec![1, 4, 5, 9],
             sequence_number: None,
+            relayed_from_opt: None,
         };
 
         let result = SequencedPacket::from(&tdm);
@@ -268,6 +269,7 @@ mod tests {
             last_data: true,
             data: vec![1, 4, 5, 9],
             sequence_number: Some(1),
+            relayed_from_opt: None,
         };
 
         let result = SequencedPacket::from(&tdm);
@@ -281,6 +283,7 @@ mod tests {
             last_data: false,
             data: vec![4, 2, 5, 67],
             sequence_number: Some(4),
+            relayed_from_opt: None,
         };
 
         let result = SequencedPacket::from(&tdm);
@@ -0,0 +1,459 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+
+use crate::sub_lib::disk_overflow::DiskOverflow;
+use crate::sub_lib::sequence_buffer::SequencedPacket;
+use std::collections::BTreeSet;
+use std::collections::VecDeque;
+use std::time::Duration;
+use std::time::Instant;
+
+// How many response packets the exit Node keeps in flight on one stream without acknowledgment
+pub const SEND_WINDOW_SIZE: usize = 32;
+// The originating Node acknowledges at least this often while packets arrive in order
+pub const ACK_INTERVAL: u64 = 8;
+pub const RETRANSMIT_TIMEOUT: Duration = Duration::from_secs(3);
+pub const RETRANSMIT_CHECK_INTERVAL: Duration = Duration::from_millis(500);
+// After this many unanswered retransmissions of one packet, the originating Node is presumed gone
+pub const MAX_RETRANSMISSIONS: u32 = 5;
+// Packets waiting for room in the window beyond this many bytes wait on disk instead
+pub const WAITING_MEMORY_LIMIT: usize = 256 * 1024;
+// The exit Node stops reading from the server while this much is waiting, and starts again once
+// acknowledgments have brought it down to the lower figure
+pub const PAUSE_READING_BYTES: usize = 1024 * 1024;
+pub const RESUME_READING_BYTES: usize = 256 * 1024;
+
+#[derive(Debug, PartialEq)]
+pub enum Retransmission {
+    Due(Vec<SequencedPacket>),
+    Abandoned,
+}
+
+struct InFlightPacket {
+    packet: SequencedPacket,
+    sent_at: Instant,
+    retransmissions: u32,
+}
+
+// The exit Node's side of a stream: packets go out as long as there's room in the window, and
+// stay in flight until the originating Node acknowledges them or they're overdue and go out again.
+pub struct SendWindow {
+    capacity: usize,
+    in_flight: VecDeque<InFlightPacket>,
+    waiting: VecDeque<SequencedPacket>,
+    waiting_bytes: usize,
+    memory_limit: usize,
+    overflow: DiskOverflow,
+    last_data_offered: bool,
+}
+
+impl SendWindow {
+    pub fn new(capacity: usize) -> SendWindow {
+        SendWindow {
+            capacity,
+            in_flight: VecDeque::new(),
+            waiting: VecDeque::new(),
+            waiting_bytes: 0,
+            memory_limit: WAITING_MEMORY_LIMIT,
+            overflow: DiskOverflow::new(&std::env::temp_dir()),
+            last_data_offered: false,
+        }
+    }
+
+    // Returns the packets that can be sent for the first time now
+    pub fn offer(&mut self, packet: SequencedPacket, now: Instant) -> Vec<SequencedPacket> {
+        self.last_data_offered = self.last_data_offered || packet.last_data;
+        // Once anything is on disk, everything after it goes there too, to keep them in order.
+        // If the disk won't take a packet, it waits in memory; its sequence number will put it
+        // back in its place at the originating Node.
+        let to_disk = !self.overflow.is_empty() || self.waiting_bytes >= self.memory_limit;
+        if !to_disk || self.overflow.push(&packet).is_err() {
+            self.waiting_bytes += packet.data.len();
+            self.waiting.push_back(packet);
+        }
+        self.fill(now)
+    }
+
+    // next_sequence_number is the lowest one the originating Node hasn't received yet
+    pub fn acknowledge(&mut self, next_sequence_number: u64, now: Instant) -> Vec<SequencedPacket> {
+        while self
+            .in_flight
+            .front()
+            .map(|in_flight| in_flight.packet.sequence_number < next_sequence_number)
+            .unwrap_or(false)
+        {
+            self.in_flight.pop_front();
+        }
+        self.fill(now)
+    }
+
+    pub fn retransmission(&mut self, now: Instant) -> Retransmission {
+        let mut due = vec![];
+        for in_flight in self.in_flight.iter_mut() {
+            if now.saturating_duration_since(in_flight.sent_at) < RETRANSMIT_TIMEOUT {
+                continue;
+            }
+            if in_flight.retransmissions >= MAX_RETRANSMISSIONS {
+                return Retransmission::Abandoned;
+            }
+            in_flight.retransmissions += 1;
+            in_flight.sent_at = now;
+            due.push(in_flight.packet.clone());
+        }
+        Retransmission::Due(due)
+    }
+
+    pub fn last_data_offered(&self) -> bool {
+        self.last_data_offered
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.last_data_offered
+            && self.in_flight.is_empty()
+            && self.waiting.is_empty()
+            && self.overflow.is_empty()
+    }
+
+    // Bytes that haven't been sent yet, in memory or on disk
+    pub fn buffered_bytes(&self) -> usize {
+        self.waiting_bytes + self.overflow.bytes()
+    }
+
+    fn fill(&mut self, now: Instant) -> Vec<SequencedPacket> {
+        let mut sendable = vec![];
+        while self.in_flight.len() < self.capacity {
+            let packet = match self.next_waiting() {
+                Some(packet) => packet,
+                None => break,
+            };
+            sendable.push(packet.clone());
+            self.in_flight.push_back(InFlightPacket {
+                packet,
+                sent_at: now,
+                retransmissions: 0,
+            });
+        }
+        sendable
+    }
+
+    fn next_waiting(&mut self) -> Option<SequencedPacket> {
+        match self.waiting.pop_front() {
+            Some(packet) => {
+                self.waiting_bytes -= packet.data.len();
+                Some(packet)
+            }
+            None => self.overflow.pop().unwrap_or(None),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Reception {
+    // false for a retransmitted packet that already arrived once
+    pub is_new: bool,
+    // The next sequence number expected, if it's time to tell the exit Node
+    pub ack_opt: Option<u64>,
+}
+
+// The originating Node's side of a stream: it weeds out duplicates and decides when to acknowledge.
+// It acknowledges right away whenever something's missing, so that the exit Node retransmits soon.
+#[derive(Default)]
+pub struct ReceiveWindow {
+    next_expected: u64,
+    received_ahead: BTreeSet<u64>,
+    unacknowledged: u64,
+    last_sequence_number_opt: Option<u64>,
+}
+
+impl ReceiveWindow {
+    pub fn new() -> ReceiveWindow {
+        ReceiveWindow::default()
+    }
+
+    // For a stream picked up where an earlier run of this Node left it
+    pub fn starting_at(next_expected: u64) -> ReceiveWindow {
+        ReceiveWindow {
+            next_expected,
+            ..ReceiveWindow::default()
+        }
+    }
+
+    pub fn receive(&mut self, sequence_number: u64, last_data: bool) -> Reception {
+        if last_data {
+            self.last_sequence_number_opt = Some(sequence_number);
+        }
+        let gap_before = !self.received_ahead.is_empty();
+        let is_new = if sequence_number < self.next_expected
+            || self.received_ahead.contains(&sequence_number)
+        {
+            false
+        } else if sequence_number == self.next_expected {
+            self.next_expected += 1;
+            while self.received_ahead.remove(&self.next_expected) {
+                self.next_expected += 1;
+            }
+            true
+        } else {
+            self.received_ahead.insert(sequence_number);
+            true
+        };
+        if is_new {
+            self.unacknowledged += 1;
+        }
+        let ack_now = !is_new
+            || gap_before
+            || !self.received_ahead.is_empty()
+            || self.is_complete()
+            || self.unacknowledged >= ACK_INTERVAL;
+        let ack_opt = if ack_now {
+            self.unacknowledged = 0;
+            Some(self.next_expected)
+        } else {
+            None
+        };
+        Reception { is_new, ack_opt }
+    }
+
+    pub fn next_expected(&self) -> u64 {
+        self.next_expected
+    }
+
+    // Everything that has arrived is in order and acknowledged, and more may come
+    pub fn is_settled(&self) -> bool {
+        self.received_ahead.is_empty()
+            && self.unacknowledged == 0
+            && self.last_sequence_number_opt.is_none()
+    }
+
+    // The last data has arrived and so has everything before it
+    pub fn is_complete(&self) -> bool {
+        match self.last_sequence_number_opt {
+            Some(last_sequence_number) => self.next_expected > last_sequence_number,
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use masq_lib::test_utils::utils::ensure_node_home_directory_exists;
+
+    fn packet(sequence_number: u64, last_data: bool) -> SequencedPacket {
+        SequencedPacket::new(vec![sequence_number as u8], sequence_number, last_data)
+    }
+
+    #[test]
+    fn send_window_holds_packets_beyond_its_capacity() {
+        let now = Instant::now();
+        let mut subject = SendWindow::new(2);
+
+        assert_eq!(subject.offer(packet(0, false), now), vec![packet(0, false)]);
+        assert_eq!(subject.offer(packet(1, false), now), vec![packet(1, false)]);
+        assert_eq!(subject.offer(packet(2, false), now), vec![]);
+        assert_eq!(subject.offer(packet(3, true), now), vec![]);
+        assert_eq!(subject.last_data_offered(), true);
+        assert_eq!(subject.is_finished(), false);
+    }
+
+    #[test]
+    fn acknowledgment_slides_send_window() {
+        let now = Instant::now();
+        let mut subject = SendWindow::new(2);
+        (0..4).for_each(|n| {
+            subject.offer(packet(n, n == 3), now);
+        });
+
+        assert_eq!(subject.acknowledge(0, now), vec![]);
+        assert_eq!(subject.acknowledge(1, now), vec![packet(2, false)]);
+        assert_eq!(subject.acknowledge(3, now), vec![packet(3, true)]);
+        assert_eq!(subject.is_finished(), false);
+        assert_eq!(subject.acknowledge(4, now), vec![]);
+        assert_eq!(subject.is_finished(), true);
+    }
+
+    #[test]
+    fn packets_beyond_the_memory_limit_wait_on_disk_and_come_back_in_order() {
+        let directory = ensure_node_home_directory_exists(
+            "sequence_window",
+            "packets_beyond_the_memory_limit_wait_on_disk_and_come_back_in_order",
+        );
+        let now = Instant::now();
+        let mut subject = SendWindow::new(1);
+        subject.memory_limit = 2;
+        subject.overflow = DiskOverflow::new(&directory);
+        (0..6).for_each(|n| {
+            subject.offer(packet(n, n == 5), now);
+        });
+
+        let waiting_in_memory = subject.waiting.len();
+        let buffered_bytes = subject.buffered_bytes();
+        let sent = (1..=6)
+            .flat_map(|n| subject.acknowledge(n, now))
+            .collect::<Vec<SequencedPacket>>();
+
+        assert_eq!(waiting_in_memory, 2);
+        assert_eq!(buffered_bytes, 5);
+        assert_eq!(sent, (1..6).map(|n| packet(n, n == 5)).collect::<Vec<_>>());
+        assert_eq!(subject.buffered_bytes(), 0);
+        assert_eq!(subject.is_finished(), true);
+    }
+
+    #[test]
+    fn overdue_packets_are_retransmitted() {
+        let then = Instant::now();
+        let mut subject = SendWindow::new(4);
+        subject.offer(packet(0, false), then);
+        subject.offer(packet(1, false), then + Duration::from_secs(2));
+
+        let early = subject.retransmission(then + Duration::from_secs(1));
+        let first = subject.retransmission(then + RETRANSMIT_TIMEOUT);
+        let second = subject.retransmission(then + Duration::from_secs(2) + RETRANSMIT_TIMEOUT);
+
+        assert_eq!(early, Retransmission::Due(vec![]));
+        assert_eq!(first, Retransmission::Due(vec![packet(0, false)]));
+        assert_eq!(second, Retransmission::Due(vec![packet(1, false)]));
+    }
+
+    #[test]
+    fn packet_retransmitted_too_often_abandons_stream() {
+        let mut now = Instant::now();
+        let mut subject = SendWindow::new(4);
+        subject.offer(packet(0, false), now);
+        for _ in 0..MAX_RETRANSMISSIONS {
+            now += RETRANSMIT_TIMEOUT;
+            assert_eq!(
+                subject.retransmission(now),
+                Retransmission::Due(vec![packet(0, false)])
+            );
+        }
+
+        let result = subject.retransmission(now + RETRANSMIT_TIMEOUT);
+
+        assert_eq!(result, Retransmission::Abandoned);
+    }
+
+    #[test]
+    fn receive_window_acknowledges_in_order_packets_at_intervals() {
+        let mut subject = ReceiveWindow::new();
+
+        let results = (0..(ACK_INTERVAL * 2))
+            .map(|n| subject.receive(n, false))
+            .collect::<Vec<Reception>>();
+
+        results.iter().enumerate().for_each(|(n, reception)| {
+            let expected_ack_opt = if (n as u64 + 1) % ACK_INTERVAL == 0 {
+                Some(n as u64 + 1)
+            } else {
+                None
+            };
+            assert_eq!(
+                reception,
+                &Reception {
+                    is_new: true,
+                    ack_opt: expected_ack_opt
+                },
+                "at sequence number {}",
+                n
+            );
+        });
+    }
+
+    #[test]
+    fn receive_window_acknowledges_gaps_and_duplicates_right_away() {
+        let mut subject = ReceiveWindow::new();
+        subject.receive(0, false);
+
+        let gap = subject.receive(2, false);
+        let duplicate = subject.receive(2, false);
+        let filled = subject.receive(1, false);
+        let stale = subject.receive(0, false);
+
+        assert_eq!(
+            gap,
+            Reception {
+                is_new: true,
+                ack_opt: Some(1)
+            }
+        );
+        assert_eq!(
+            duplicate,
+            Reception {
+                is_new: false,
+                ack_opt: Some(1)
+            }
+        );
+        assert_eq!(
+            filled,
+            Reception {
+                is_new: true,
+                ack_opt: Some(3)
+            }
+        );
+        assert_eq!(
+            stale,
+            Reception {
+                is_new: false,
+                ack_opt: Some(3)
+            }
+        );
+    }
+
+    #[test]
+    fn receive_window_is_complete_only_when_nothing_before_last_data_is_missing() {
+        let mut subject = ReceiveWindow::new();
+        subject.receive(0, false);
+
+        let early_last = subject.receive(2, true);
+        let complete_before = subject.is_complete();
+        let filled = subject.receive(1, false);
+
+        assert_eq!(
+            early_last,
+            Reception {
+                is_new: true,
+                ack_opt: Some(1)
+            }
+        );
+        assert_eq!(complete_before, false);
+        assert_eq!(
+            filled,
+            Reception {
+                is_new: true,
+                ack_opt: Some(3)
+            }
+        );
+        assert_eq!(subject.is_complete(), true);
+    }
+
+    #[test]
+    fn receive_window_can_start_where_an_earlier_run_left_off_and_knows_when_it_is_settled() {
+        let mut subject = ReceiveWindow::starting_at(10);
+        let settled_at_start = subject.is_settled();
+
+        let retransmission = subject.receive(9, false);
+        let next = subject.receive(10, false);
+        let settled_with_unacknowledged = subject.is_settled();
+
+        assert_eq!(settled_at_start, true);
+        assert_eq!(
+            retransmission,
+            Reception {
+                is_new: false,
+                ack_opt: Some(10)
+            }
+        );
+        assert_eq!(
+            next,
+            Reception {
+                is_new: true,
+                ack_opt: None
+            }
+        );
+        assert_eq!(subject.next_expected(), 11);
+        assert_eq!(settled_with_unacknowledged, false);
+        subject.receive(10, false);
+        assert_eq!(subject.is_settled(), true);
+        subject.receive(12, true);
+        assert_eq!(subject.is_settled(), false);
+    }
+}
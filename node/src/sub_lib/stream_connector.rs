@@ -1,5 +1,6 @@
 // Copyright (c) 2017-2019, Substratum LLC (https://substratum.net) and/or its affiliates. All rights reserved.
 use crate::sub_lib::logger::Logger;
+use crate::sub_lib::path_mtu;
 use crate::sub_lib::tokio_wrappers::ReadHalfWrapper;
 use crate::sub_lib::tokio_wrappers::ReadHalfWrapperReal;
 use crate::sub_lib::tokio_wrappers::WriteHalfWrapper;
@@ -17,6 +18,8 @@ use tokio::reactor::Handle;
 use tokio::timer::Timeout;
 
 pub const CONNECT_TIMEOUT_MS: u64 = 5000;
+// Keeps NAT gateways and firewalls from forgetting long-lived connections that happen to be idle
+pub const TCP_KEEPALIVE_SECS: u64 = 60;
 pub type ConnectionInfoFuture = Box<dyn Future<Item = ConnectionInfo, Error = io::Error> + Send>;
 
 pub struct ConnectionInfo {
@@ -44,9 +47,13 @@ pub struct StreamConnectorReal {}
 impl StreamConnector for StreamConnectorReal {
     fn connect(&self, socket_addr: SocketAddr, logger: &Logger) -> ConnectionInfoFuture {
         let future_logger = logger.clone();
+        let connect_future = match path_mtu::socket_fitted_to_path(socket_addr, logger) {
+            Some(socket) => TcpStream::connect_std(socket, &socket_addr, &Handle::default()),
+            None => TcpStream::connect(&socket_addr),
+        };
         Box::new(
             Timeout::new(
-                TcpStream::connect(&socket_addr).then(move |result| match result {
+                connect_future.then(move |result| match result {
                     Ok(stream) => {
                         let local_addr = stream.local_addr().unwrap_or_else(|_| {
                             panic!(
@@ -65,6 +72,8 @@ impl StreamConnector for StreamConnectorReal {
                                 return Err(e);
                             }
                         };
+                        Self::enable_keepalive(&stream, &future_logger);
+                        path_mtu::learn_path_mtu(&stream);
                         let (read_half, write_half) = stream.split();
                         Ok(ConnectionInfo {
                             reader: Box::new(ReadHalfWrapperReal::new(read_half)),
@@ -140,6 +149,7 @@ impl StreamConnector for StreamConnectorReal {
                 return None;
             }
         };
+        Self::enable_keepalive(&stream, logger);
         let (read_half, write_half) = stream.split();
         Some(ConnectionInfo {
             reader: Box::new(ReadHalfWrapperReal::new(read_half)),
@@ -150,6 +160,19 @@ impl StreamConnector for StreamConnectorReal {
     }
 }
 
+impl StreamConnectorReal {
+    fn enable_keepalive(stream: &TcpStream, logger: &Logger) {
+        if let Err(e) = stream.set_keepalive(Some(Duration::from_secs(TCP_KEEPALIVE_SECS))) {
+            warning!(
+                logger,
+                "Could not enable TCP keepalive on stream to {:?}: {}",
+                stream.peer_addr().ok(),
+                e
+            );
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -315,6 +338,21 @@ mod tests {
         TestLogHandler::new().exists_log_matching("Could not connect to any of the IP addresses supplied for some hostname: \\[\"255\\.255\\.255\\.255:\\d+\"\\]");
     }
 
+    #[test]
+    fn enable_keepalive_turns_on_tcp_keepalive() {
+        let server = LittleTcpServer::start();
+        let std_stream = StdTcpStream::connect(server.socket_addr()).unwrap();
+        let stream = TcpStream::from_std(std_stream, &Handle::default()).unwrap();
+        stream.set_keepalive(None).unwrap();
+
+        StreamConnectorReal::enable_keepalive(&stream, &Logger::new("test"));
+
+        assert_eq!(
+            stream.keepalive().unwrap(),
+            Some(Duration::from_secs(TCP_KEEPALIVE_SECS))
+        );
+    }
+
     #[test]
     fn closed_stream_either_splits_properly_or_doesnt_split_and_logs() {
         init_test_logging();
@@ -2,6 +2,7 @@
 use crate::sub_lib::dispatcher::Endpoint;
 use crate::sub_lib::neighborhood::NodeQueryResponseMetadata;
 use actix::Message;
+use std::net::{IpAddr, SocketAddr};
 
 #[derive(PartialEq, Debug, Message, Clone)]
 pub struct TransmitDataMsg {
@@ -9,6 +10,16 @@ pub struct TransmitDataMsg {
     pub last_data: bool,
     pub sequence_number: Option<u64>, // Some implies clear data; None implies clandestine.
     pub data: Vec<u8>,
+    // The neighbor whose package this Node is passing on; None for this Node's own traffic
+    pub relayed_from_opt: Option<IpAddr>,
+}
+
+// Connect to peer_addr from local_port at the same moment peer_addr is connecting to us, so that
+// both NATs let the connection through
+#[derive(PartialEq, Debug, Message, Clone)]
+pub struct PunchHoleMsg {
+    pub peer_addr: SocketAddr,
+    pub local_port: u16,
 }
 
 #[derive(Message, Clone)]
@@ -90,6 +90,16 @@ impl StreamKey {
             hash: hash.digest().bytes(),
         }
     }
+
+    // A different key for the same client connection, when its stream starts over on a new route
+    pub fn retried(&self, attempt: u32) -> StreamKey {
+        let mut hash = sha1::Sha1::new();
+        hash.update(&self.hash);
+        hash.update(&attempt.to_be_bytes());
+        StreamKey {
+            hash: hash.digest().bytes(),
+        }
+    }
 }
 
 type HashType = [u8; sha1::DIGEST_LENGTH];
@@ -146,6 +156,22 @@ mod tests {
         assert_ne!(one, another);
     }
 
+    #[test]
+    fn retried_keys_differ_from_the_original_and_from_each_other() {
+        let subject = StreamKey::new(
+            PublicKey::new(&b"These are the times"[..]),
+            SocketAddr::from_str("2.3.4.5:6789").unwrap(),
+        );
+
+        let first = subject.retried(1);
+        let second = subject.retried(2);
+
+        assert_ne!(first, subject);
+        assert_ne!(second, subject);
+        assert_ne!(first, second);
+        assert_eq!(subject.retried(1), first);
+    }
+
     #[test]
     fn debug_implementation() {
         let key = PublicKey::new(&b"These are the times"[..]);
@@ -0,0 +1,84 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use crate::sub_lib::proxy_server::ProxyProtocol;
+use crate::sub_lib::stream_key::StreamKey;
+use masq_lib::messages::UiStream;
+use std::time::Instant;
+
+// What a UI can find out about a stream while it's open
+#[derive(Clone, Debug, PartialEq)]
+pub struct StreamStats {
+    pub protocol: ProxyProtocol,
+    pub target_host_opt: Option<String>,
+    pub hop_count_opt: Option<usize>,
+    pub bytes_out: u64,
+    pub bytes_in: u64,
+    pub opened_at: Instant,
+}
+
+impl StreamStats {
+    pub fn new(
+        protocol: ProxyProtocol,
+        target_host_opt: Option<String>,
+        opened_at: Instant,
+    ) -> StreamStats {
+        StreamStats {
+            protocol,
+            target_host_opt,
+            hop_count_opt: None,
+            bytes_out: 0,
+            bytes_in: 0,
+            opened_at,
+        }
+    }
+
+    pub fn to_ui_stream(&self, stream_key: &StreamKey, now: Instant) -> UiStream {
+        UiStream {
+            stream_key: stream_key.to_string(),
+            protocol: format!("{:?}", self.protocol),
+            target_host_opt: self.target_host_opt.clone(),
+            bytes_out: self.bytes_out,
+            bytes_in: self.bytes_in,
+            hop_count_opt: self.hop_count_opt,
+            age_seconds: now
+                .checked_duration_since(self.opened_at)
+                .map(|age| age.as_secs())
+                .unwrap_or(0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::make_meaningless_stream_key;
+    use std::time::Duration;
+
+    #[test]
+    fn stats_become_a_ui_stream() {
+        let stream_key = make_meaningless_stream_key();
+        let opened_at = Instant::now();
+        let mut subject = StreamStats::new(
+            ProxyProtocol::TLS,
+            Some("example.com".to_string()),
+            opened_at,
+        );
+        subject.hop_count_opt = Some(3);
+        subject.bytes_out = 1234;
+        subject.bytes_in = 56789;
+
+        let result = subject.to_ui_stream(&stream_key, opened_at + Duration::from_millis(65_500));
+
+        assert_eq!(
+            result,
+            UiStream {
+                stream_key: stream_key.to_string(),
+                protocol: "TLS".to_string(),
+                target_host_opt: Some("example.com".to_string()),
+                bytes_out: 1234,
+                bytes_in: 56789,
+                hop_count_opt: Some(3),
+                age_seconds: 65,
+            }
+        );
+    }
+}
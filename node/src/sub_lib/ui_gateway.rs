@@ -2,16 +2,48 @@
 
 use crate::sub_lib::peer_actors::BindMessage;
 use actix::Recipient;
+use masq_lib::ip_network::IpNetwork;
 use masq_lib::ui_gateway::{NodeFromUiMessage, NodeToUiMessage};
 use std::fmt::{Debug, Formatter};
+use std::net::IpAddr;
+use std::path::PathBuf;
 
 #[derive(Clone, Debug)]
 pub struct UiGatewayConfig {
     pub ui_port: u16,
     pub node_descriptor: String, // TODO: This really shouldn't be here; it exists only to answer
-                                 // the GetNodeDescriptor message, which A) is part of MASQNode-UI,
-                                 // and B) shouldn't be answered by the UiGateway anyway. Move it
-                                 // to the Dispatcher part of the BootstrapperConfig.
+    // the GetNodeDescriptor message, which A) is part of MASQNode-UI,
+    // and B) shouldn't be answered by the UiGateway anyway. Move it
+    // to the Dispatcher part of the BootstrapperConfig.
+    pub tls_opt: Option<UiTlsConfig>,
+    pub auth_opt: Option<UiAuthConfig>,
+    pub rest_port_opt: Option<u16>,
+    pub grpc_port_opt: Option<u16>,
+    pub health_port_opt: Option<u16>,
+    // Where the health checks look for the database
+    pub data_directory: PathBuf,
+}
+
+// The passwords UIs must present before the UI port will do anything for them
+#[derive(Clone, Debug, PartialEq)]
+pub struct UiAuthConfig {
+    pub full_password: String,
+    pub read_only_password_opt: Option<String>,
+}
+
+// Where the UI port's certificate and private key live. If generate is set, a self-signed pair is
+// made there the first time it's needed; otherwise both files must be supplied by the user.
+// The UI port listens on all of interfaces (or every interface, if it's empty), takes connections
+// only from localhost and the allowlist (or from anywhere, if it's empty), and insists on a client
+// certificate signed by the CA in client_ca_path_opt, if there is one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UiTlsConfig {
+    pub certificate_path: PathBuf,
+    pub key_path: PathBuf,
+    pub generate: bool,
+    pub interfaces: Vec<IpAddr>,
+    pub allowlist: Vec<IpNetwork>,
+    pub client_ca_path_opt: Option<PathBuf>,
 }
 
 #[derive(Clone)]
@@ -58,6 +58,13 @@ pub fn time_t_timestamp() -> u32 {
         .as_secs() as u32
 }
 
+pub fn millis_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("bad interval")
+        .as_millis() as u64
+}
+
 pub fn make_printable_string(bytes: &[u8]) -> String {
     let strs: Vec<String> = bytes
         .iter()
@@ -65,6 +65,10 @@ impl FromStr for DataVersion {
                 "DataVersion syntax is <major>.<minor>, not '{}'",
                 s
             )),
+            Some(ref numbers) if numbers.iter().any(|n| *n > 4095) => Err(format!(
+                "DataVersion major and minor components range from 0-4095, not '{}'",
+                s
+            )),
             Some(numbers) => Ok(DataVersion::new(numbers[0], numbers[1])),
         }
     }
@@ -626,6 +630,19 @@ mod tests {
         );
     }
 
+    #[test]
+    fn dataversions_arent_parsed_when_components_are_out_of_range() {
+        let result = DataVersion::from_str("4096.2");
+
+        assert_eq!(
+            result,
+            Err(
+                "DataVersion major and minor components range from 0-4095, not '4096.2'"
+                    .to_string()
+            )
+        );
+    }
+
     #[test]
     #[should_panic(expected = "A migration step from 1.1 to 1.1 is useless and can't be added")]
     fn migration_steps_cant_be_added_from_and_to_the_same_version() {
@@ -3,6 +3,7 @@ use crate::blockchain::bip32::Bip32ECKeyPair;
 use crate::blockchain::payer::Payer;
 use crate::sub_lib::cryptde;
 use crate::sub_lib::cryptde::PublicKey as CryptdePublicKey;
+use crate::sub_lib::descriptor_signature;
 use ethsign::{PublicKey, Signature};
 use rusqlite::types::{FromSql, FromSqlError, ToSqlOutput, Value, ValueRef};
 use rusqlite::ToSql;
@@ -429,6 +430,34 @@ impl Serialize for Wallet {
     }
 }
 
+// A Node's statement that from effective_block on it should be paid at its current earning wallet
+// instead of previous_wallet. The Node's own key can't vouch for that, or any Node could claim any
+// wallet's debts; so signature is the previous wallet's signature of message(), made with its
+// "sign message" feature.
+#[derive(Clone, Debug, PartialEq, Eq, serde_derive::Serialize, serde_derive::Deserialize)]
+pub struct WalletChange {
+    pub previous_wallet: Wallet,
+    pub effective_block: u64,
+    pub signature: String,
+}
+
+impl WalletChange {
+    pub fn message(previous_wallet: &Wallet, current_wallet: &Wallet) -> String {
+        format!(
+            "Pay what is owed to {} to {} instead",
+            previous_wallet, current_wallet
+        )
+    }
+
+    pub fn is_signed(&self, current_wallet: &Wallet) -> bool {
+        descriptor_signature::is_signed_by(
+            &Self::message(&self.previous_wallet, current_wallet),
+            &self.previous_wallet,
+            &self.signature,
+        )
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -872,4 +901,48 @@ mod tests {
         assert!(!uninitialized_a1.congruent(&public_key_a1));
         assert!(!public_key_a1.congruent(&uninitialized_a1));
     }
+
+    #[test]
+    fn wallet_change_is_signed_only_by_the_previous_wallet_and_only_for_the_current_one() {
+        let previous_wallet = make_paying_wallet(b"previous");
+        let current_wallet = make_wallet("current");
+        let message = WalletChange::message(&previous_wallet, &current_wallet);
+        let signature = descriptor_signature::sign_message(&message, &previous_wallet).unwrap();
+        let subject = WalletChange {
+            previous_wallet: previous_wallet.as_address_wallet(),
+            effective_block: 1234,
+            signature,
+        };
+        let thief_wallet = make_paying_wallet(b"thief");
+        let forged = WalletChange {
+            signature: descriptor_signature::sign_message(&message, &thief_wallet).unwrap(),
+            ..subject.clone()
+        };
+
+        assert_eq!(subject.is_signed(&current_wallet), true);
+        assert_eq!(subject.is_signed(&make_wallet("thief")), false);
+        assert_eq!(forged.is_signed(&current_wallet), false);
+        assert_eq!(
+            WalletChange {
+                signature: "0x1234".to_string(),
+                ..subject
+            }
+            .is_signed(&current_wallet),
+            false
+        );
+    }
+
+    #[test]
+    fn wallet_change_survives_serialization() {
+        let subject = WalletChange {
+            previous_wallet: make_wallet("previous"),
+            effective_block: 4321,
+            signature: "0x0102".to_string(),
+        };
+
+        let serialized = serde_cbor::ser::to_vec(&subject).unwrap();
+        let result: WalletChange = serde_cbor::de::from_slice(&serialized).unwrap();
+
+        assert_eq!(result, subject);
+    }
 }
@@ -13,6 +13,8 @@ pub mod recorder;
 pub mod stream_connector_mock;
 pub mod tcp_wrapper_mocks;
 pub mod tokio_wrapper_mocks;
+#[cfg(any(test, feature = "virtual_time"))]
+pub mod virtual_clock;
 
 use crate::blockchain::bip32::Bip32ECKeyPair;
 use crate::blockchain::blockchain_interface::contract_address;
@@ -280,6 +282,7 @@ pub fn make_request_payload(bytes: usize, cryptde: &dyn CryptDE) -> ClientReques
         target_port: HTTP_PORT,
         protocol: ProxyProtocol::HTTP,
         originator_public_key: cryptde.public_key().clone(),
+        is_resumption: false,
     }
 }
 
@@ -265,6 +265,7 @@ impl NeighborhoodDatabase {
         let node_ref = self.node_by_key_mut(public_key).unwrap();
         node_ref.signed_gossip = node_record.signed_gossip;
         node_ref.signature = node_record.signature;
+        node_ref.node_addr_signature_opt = node_record.node_addr_signature_opt;
     }
 }
 
@@ -274,6 +275,7 @@ impl From<&NodeRecord> for AccessibleGossipRecord {
             signed_gossip: node_record.signed_gossip.clone(),
             signature: node_record.signature.clone(),
             node_addr_opt: node_record.node_addr_opt(),
+            node_addr_signature_opt: node_record.node_addr_signature_opt.clone(),
             inner: node_record.inner.clone(),
         }
     }
@@ -3,7 +3,7 @@
 use crate::db_config::persistent_configuration::{PersistentConfigError, PersistentConfiguration};
 use crate::sub_lib::cryptde::PlainData;
 use crate::sub_lib::neighborhood::NodeDescriptor;
-use crate::sub_lib::wallet::Wallet;
+use crate::sub_lib::wallet::{Wallet, WalletChange};
 use std::cell::RefCell;
 use std::sync::{Arc, Mutex};
 
@@ -17,6 +17,8 @@ pub struct PersistentConfigurationMock {
     check_password_results: RefCell<Vec<Result<bool, PersistentConfigError>>>,
     change_password_params: Arc<Mutex<Vec<(Option<String>, String)>>>,
     change_password_results: RefCell<Vec<Result<(), PersistentConfigError>>>,
+    upgrade_password_protection_params: Arc<Mutex<Vec<String>>>,
+    upgrade_password_protection_results: RefCell<Vec<Result<bool, PersistentConfigError>>>,
     clandestine_port_results: RefCell<Vec<Result<Option<u16>, PersistentConfigError>>>,
     set_clandestine_port_params: Arc<Mutex<Vec<u16>>>,
     set_clandestine_port_results: RefCell<Vec<Result<(), PersistentConfigError>>>,
@@ -42,14 +44,43 @@ pub struct PersistentConfigurationMock {
     earning_wallet_address_results: RefCell<Vec<Result<Option<String>, PersistentConfigError>>>,
     set_earning_wallet_address_params: Arc<Mutex<Vec<String>>>,
     set_earning_wallet_address_results: RefCell<Vec<Result<(), PersistentConfigError>>>,
+    earning_wallet_change_results:
+        RefCell<Vec<Result<Option<WalletChange>, PersistentConfigError>>>,
+    change_earning_wallet_address_params: Arc<Mutex<Vec<(String, u64, String)>>>,
+    change_earning_wallet_address_results: RefCell<Vec<Result<(), PersistentConfigError>>>,
     past_neighbors_params: Arc<Mutex<Vec<String>>>,
     past_neighbors_results:
         RefCell<Vec<Result<Option<Vec<NodeDescriptor>>, PersistentConfigError>>>,
     set_past_neighbors_params: Arc<Mutex<Vec<(Option<Vec<NodeDescriptor>>, String)>>>,
     set_past_neighbors_results: RefCell<Vec<Result<(), PersistentConfigError>>>,
+    clandestine_keys_params: Arc<Mutex<Vec<String>>>,
+    clandestine_keys_results: RefCell<Vec<Result<Option<PlainData>, PersistentConfigError>>>,
+    set_clandestine_keys_params: Arc<Mutex<Vec<(Option<PlainData>, String)>>>,
+    set_clandestine_keys_results: RefCell<Vec<Result<(), PersistentConfigError>>>,
+    split_tunnel_rules_results: RefCell<Vec<Result<Option<String>, PersistentConfigError>>>,
+    set_split_tunnel_rules_params: Arc<Mutex<Vec<Option<String>>>>,
+    set_split_tunnel_rules_results: RefCell<Vec<Result<(), PersistentConfigError>>>,
+    application_rules_results: RefCell<Vec<Result<Option<String>, PersistentConfigError>>>,
+    set_application_rules_params: Arc<Mutex<Vec<Option<String>>>>,
+    set_application_rules_results: RefCell<Vec<Result<(), PersistentConfigError>>>,
     start_block_results: RefCell<Vec<Result<Option<u64>, PersistentConfigError>>>,
     set_start_block_params: Arc<Mutex<Vec<u64>>>,
     set_start_block_results: RefCell<Vec<Result<(), PersistentConfigError>>>,
+    log_rotation_size_mb_results: RefCell<Vec<Result<Option<u64>, PersistentConfigError>>>,
+    set_log_rotation_size_mb_params: Arc<Mutex<Vec<u64>>>,
+    set_log_rotation_size_mb_results: RefCell<Vec<Result<(), PersistentConfigError>>>,
+    log_rotation_age_hours_results: RefCell<Vec<Result<Option<u64>, PersistentConfigError>>>,
+    set_log_rotation_age_hours_params: Arc<Mutex<Vec<u64>>>,
+    set_log_rotation_age_hours_results: RefCell<Vec<Result<(), PersistentConfigError>>>,
+    log_retention_results: RefCell<Vec<Result<Option<u64>, PersistentConfigError>>>,
+    set_log_retention_params: Arc<Mutex<Vec<u64>>>,
+    set_log_retention_results: RefCell<Vec<Result<(), PersistentConfigError>>>,
+    payment_token_results: RefCell<Vec<Result<Option<String>, PersistentConfigError>>>,
+    set_payment_token_params: Arc<Mutex<Vec<String>>>,
+    set_payment_token_results: RefCell<Vec<Result<(), PersistentConfigError>>>,
+    db_password_failures_results: RefCell<Vec<Result<(u64, u64), PersistentConfigError>>>,
+    set_db_password_failures_params: Arc<Mutex<Vec<(u64, u64)>>>,
+    set_db_password_failures_results: RefCell<Vec<Result<(), PersistentConfigError>>>,
 }
 
 impl PersistentConfiguration for PersistentConfigurationMock {
@@ -77,6 +108,23 @@ impl PersistentConfiguration for PersistentConfigurationMock {
         self.change_password_results.borrow_mut().remove(0)
     }
 
+    fn upgrade_password_protection(
+        &mut self,
+        db_password: &str,
+    ) -> Result<bool, PersistentConfigError> {
+        self.upgrade_password_protection_params
+            .lock()
+            .unwrap()
+            .push(db_password.to_string());
+        // Only tests that care about upgrades prepare results; for the rest there's nothing to upgrade
+        let mut results = self.upgrade_password_protection_results.borrow_mut();
+        if results.is_empty() {
+            Ok(false)
+        } else {
+            results.remove(0)
+        }
+    }
+
     fn clandestine_port(&self) -> Result<Option<u16>, PersistentConfigError> {
         Self::result_from(&self.clandestine_port_results)
     }
@@ -173,6 +221,30 @@ impl PersistentConfiguration for PersistentConfigurationMock {
             .remove(0)
     }
 
+    fn earning_wallet_change(&self) -> Result<Option<WalletChange>, PersistentConfigError> {
+        if self.earning_wallet_change_results.borrow().is_empty() {
+            return Ok(None);
+        }
+        Self::result_from(&self.earning_wallet_change_results)
+    }
+
+    fn change_earning_wallet_address(
+        &mut self,
+        new_address: &str,
+        effective_block: u64,
+        signature: &str,
+    ) -> Result<(), PersistentConfigError> {
+        self.change_earning_wallet_address_params
+            .lock()
+            .unwrap()
+            .push((
+                new_address.to_string(),
+                effective_block,
+                signature.to_string(),
+            ));
+        Self::result_from(&self.change_earning_wallet_address_results)
+    }
+
     fn past_neighbors(
         &self,
         db_password: &str,
@@ -196,6 +268,59 @@ impl PersistentConfiguration for PersistentConfigurationMock {
         self.set_past_neighbors_results.borrow_mut().remove(0)
     }
 
+    fn clandestine_keys(
+        &self,
+        db_password: &str,
+    ) -> Result<Option<PlainData>, PersistentConfigError> {
+        self.clandestine_keys_params
+            .lock()
+            .unwrap()
+            .push(db_password.to_string());
+        self.clandestine_keys_results.borrow_mut().remove(0)
+    }
+
+    fn set_clandestine_keys(
+        &mut self,
+        keys_opt: Option<PlainData>,
+        db_password: &str,
+    ) -> Result<(), PersistentConfigError> {
+        self.set_clandestine_keys_params
+            .lock()
+            .unwrap()
+            .push((keys_opt, db_password.to_string()));
+        self.set_clandestine_keys_results.borrow_mut().remove(0)
+    }
+
+    fn split_tunnel_rules(&self) -> Result<Option<String>, PersistentConfigError> {
+        Self::result_from(&self.split_tunnel_rules_results)
+    }
+
+    fn set_split_tunnel_rules(
+        &mut self,
+        rules_opt: Option<String>,
+    ) -> Result<(), PersistentConfigError> {
+        self.set_split_tunnel_rules_params
+            .lock()
+            .unwrap()
+            .push(rules_opt);
+        Self::result_from(&self.set_split_tunnel_rules_results)
+    }
+
+    fn application_rules(&self) -> Result<Option<String>, PersistentConfigError> {
+        Self::result_from(&self.application_rules_results)
+    }
+
+    fn set_application_rules(
+        &mut self,
+        rules_opt: Option<String>,
+    ) -> Result<(), PersistentConfigError> {
+        self.set_application_rules_params
+            .lock()
+            .unwrap()
+            .push(rules_opt);
+        Self::result_from(&self.set_application_rules_results)
+    }
+
     fn start_block(&self) -> Result<Option<u64>, PersistentConfigError> {
         if self.start_block_results.borrow().is_empty() {
             return Ok(Some(0));
@@ -207,6 +332,96 @@ impl PersistentConfiguration for PersistentConfigurationMock {
         self.set_start_block_params.lock().unwrap().push(value);
         Self::result_from(&self.set_start_block_results)
     }
+    fn log_rotation_size_mb(&self) -> Result<Option<u64>, PersistentConfigError> {
+        if self.log_rotation_size_mb_results.borrow().is_empty() {
+            return Ok(None);
+        }
+        Self::result_from(&self.log_rotation_size_mb_results)
+    }
+
+    fn set_log_rotation_size_mb(&mut self, value: u64) -> Result<(), PersistentConfigError> {
+        self.set_log_rotation_size_mb_params
+            .lock()
+            .unwrap()
+            .push(value);
+        if self.set_log_rotation_size_mb_results.borrow().is_empty() {
+            return Ok(());
+        }
+        Self::result_from(&self.set_log_rotation_size_mb_results)
+    }
+
+    fn log_rotation_age_hours(&self) -> Result<Option<u64>, PersistentConfigError> {
+        if self.log_rotation_age_hours_results.borrow().is_empty() {
+            return Ok(None);
+        }
+        Self::result_from(&self.log_rotation_age_hours_results)
+    }
+
+    fn set_log_rotation_age_hours(&mut self, value: u64) -> Result<(), PersistentConfigError> {
+        self.set_log_rotation_age_hours_params
+            .lock()
+            .unwrap()
+            .push(value);
+        if self.set_log_rotation_age_hours_results.borrow().is_empty() {
+            return Ok(());
+        }
+        Self::result_from(&self.set_log_rotation_age_hours_results)
+    }
+
+    fn log_retention(&self) -> Result<Option<u64>, PersistentConfigError> {
+        if self.log_retention_results.borrow().is_empty() {
+            return Ok(None);
+        }
+        Self::result_from(&self.log_retention_results)
+    }
+
+    fn set_log_retention(&mut self, value: u64) -> Result<(), PersistentConfigError> {
+        self.set_log_retention_params.lock().unwrap().push(value);
+        if self.set_log_retention_results.borrow().is_empty() {
+            return Ok(());
+        }
+        Self::result_from(&self.set_log_retention_results)
+    }
+
+    fn payment_token(&self) -> Result<Option<String>, PersistentConfigError> {
+        if self.payment_token_results.borrow().is_empty() {
+            return Ok(None);
+        }
+        Self::result_from(&self.payment_token_results)
+    }
+
+    fn set_payment_token(&mut self, token: &str) -> Result<(), PersistentConfigError> {
+        self.set_payment_token_params
+            .lock()
+            .unwrap()
+            .push(token.to_string());
+        if self.set_payment_token_results.borrow().is_empty() {
+            return Ok(());
+        }
+        Self::result_from(&self.set_payment_token_results)
+    }
+
+    fn db_password_failures(&self) -> Result<(u64, u64), PersistentConfigError> {
+        if self.db_password_failures_results.borrow().is_empty() {
+            return Ok((0, 0));
+        }
+        Self::result_from(&self.db_password_failures_results)
+    }
+
+    fn set_db_password_failures(
+        &mut self,
+        failures: u64,
+        retry_after: u64,
+    ) -> Result<(), PersistentConfigError> {
+        self.set_db_password_failures_params
+            .lock()
+            .unwrap()
+            .push((failures, retry_after));
+        if self.set_db_password_failures_results.borrow().is_empty() {
+            return Ok(());
+        }
+        Self::result_from(&self.set_db_password_failures_results)
+    }
 }
 
 impl PersistentConfigurationMock {
@@ -238,6 +453,24 @@ impl PersistentConfigurationMock {
         self
     }
 
+    pub fn upgrade_password_protection_params(
+        mut self,
+        params: &Arc<Mutex<Vec<String>>>,
+    ) -> PersistentConfigurationMock {
+        self.upgrade_password_protection_params = params.clone();
+        self
+    }
+
+    pub fn upgrade_password_protection_result(
+        self,
+        result: Result<bool, PersistentConfigError>,
+    ) -> PersistentConfigurationMock {
+        self.upgrade_password_protection_results
+            .borrow_mut()
+            .push(result);
+        self
+    }
+
     pub fn check_password_params(
         mut self,
         params: &Arc<Mutex<Vec<Option<String>>>>,
@@ -397,6 +630,39 @@ impl PersistentConfigurationMock {
         self
     }
 
+    pub fn clandestine_keys_params(
+        mut self,
+        params: &Arc<Mutex<Vec<String>>>,
+    ) -> PersistentConfigurationMock {
+        self.clandestine_keys_params = params.clone();
+        self
+    }
+
+    pub fn clandestine_keys_result(
+        self,
+        result: Result<Option<PlainData>, PersistentConfigError>,
+    ) -> PersistentConfigurationMock {
+        self.clandestine_keys_results.borrow_mut().push(result);
+        self
+    }
+
+    #[allow(clippy::type_complexity)]
+    pub fn set_clandestine_keys_params(
+        mut self,
+        params: &Arc<Mutex<Vec<(Option<PlainData>, String)>>>,
+    ) -> PersistentConfigurationMock {
+        self.set_clandestine_keys_params = params.clone();
+        self
+    }
+
+    pub fn set_clandestine_keys_result(
+        self,
+        result: Result<(), PersistentConfigError>,
+    ) -> PersistentConfigurationMock {
+        self.set_clandestine_keys_results.borrow_mut().push(result);
+        self
+    }
+
     pub fn set_consuming_wallet_derivation_path_params(
         mut self,
         params: &Arc<Mutex<Vec<(String, String)>>>,
@@ -471,6 +737,77 @@ impl PersistentConfigurationMock {
         self
     }
 
+    pub fn earning_wallet_change_result(
+        self,
+        result: Result<Option<WalletChange>, PersistentConfigError>,
+    ) -> PersistentConfigurationMock {
+        self.earning_wallet_change_results.borrow_mut().push(result);
+        self
+    }
+
+    #[allow(clippy::type_complexity)]
+    pub fn change_earning_wallet_address_params(
+        mut self,
+        params: &Arc<Mutex<Vec<(String, u64, String)>>>,
+    ) -> PersistentConfigurationMock {
+        self.change_earning_wallet_address_params = params.clone();
+        self
+    }
+
+    pub fn change_earning_wallet_address_result(
+        self,
+        result: Result<(), PersistentConfigError>,
+    ) -> PersistentConfigurationMock {
+        self.change_earning_wallet_address_results
+            .borrow_mut()
+            .push(result);
+        self
+    }
+
+    pub fn split_tunnel_rules_result(
+        self,
+        result: Result<Option<String>, PersistentConfigError>,
+    ) -> Self {
+        self.split_tunnel_rules_results.borrow_mut().push(result);
+        self
+    }
+
+    pub fn set_split_tunnel_rules_params(
+        mut self,
+        params: &Arc<Mutex<Vec<Option<String>>>>,
+    ) -> PersistentConfigurationMock {
+        self.set_split_tunnel_rules_params = params.clone();
+        self
+    }
+
+    pub fn set_split_tunnel_rules_result(self, result: Result<(), PersistentConfigError>) -> Self {
+        self.set_split_tunnel_rules_results
+            .borrow_mut()
+            .push(result);
+        self
+    }
+
+    pub fn application_rules_result(
+        self,
+        result: Result<Option<String>, PersistentConfigError>,
+    ) -> Self {
+        self.application_rules_results.borrow_mut().push(result);
+        self
+    }
+
+    pub fn set_application_rules_params(
+        mut self,
+        params: &Arc<Mutex<Vec<Option<String>>>>,
+    ) -> PersistentConfigurationMock {
+        self.set_application_rules_params = params.clone();
+        self
+    }
+
+    pub fn set_application_rules_result(self, result: Result<(), PersistentConfigError>) -> Self {
+        self.set_application_rules_results.borrow_mut().push(result);
+        self
+    }
+
     pub fn start_block_result(self, result: Result<Option<u64>, PersistentConfigError>) -> Self {
         self.start_block_results.borrow_mut().push(result);
         self
@@ -489,6 +826,125 @@ impl PersistentConfigurationMock {
         self
     }
 
+    pub fn log_rotation_size_mb_result(
+        self,
+        result: Result<Option<u64>, PersistentConfigError>,
+    ) -> Self {
+        self.log_rotation_size_mb_results.borrow_mut().push(result);
+        self
+    }
+
+    pub fn set_log_rotation_size_mb_params(
+        mut self,
+        params: &Arc<Mutex<Vec<u64>>>,
+    ) -> PersistentConfigurationMock {
+        self.set_log_rotation_size_mb_params = params.clone();
+        self
+    }
+
+    pub fn set_log_rotation_size_mb_result(
+        self,
+        result: Result<(), PersistentConfigError>,
+    ) -> Self {
+        self.set_log_rotation_size_mb_results
+            .borrow_mut()
+            .push(result);
+        self
+    }
+
+    pub fn log_rotation_age_hours_result(
+        self,
+        result: Result<Option<u64>, PersistentConfigError>,
+    ) -> Self {
+        self.log_rotation_age_hours_results
+            .borrow_mut()
+            .push(result);
+        self
+    }
+
+    pub fn set_log_rotation_age_hours_params(
+        mut self,
+        params: &Arc<Mutex<Vec<u64>>>,
+    ) -> PersistentConfigurationMock {
+        self.set_log_rotation_age_hours_params = params.clone();
+        self
+    }
+
+    pub fn set_log_rotation_age_hours_result(
+        self,
+        result: Result<(), PersistentConfigError>,
+    ) -> Self {
+        self.set_log_rotation_age_hours_results
+            .borrow_mut()
+            .push(result);
+        self
+    }
+
+    pub fn log_retention_result(self, result: Result<Option<u64>, PersistentConfigError>) -> Self {
+        self.log_retention_results.borrow_mut().push(result);
+        self
+    }
+
+    pub fn set_log_retention_params(
+        mut self,
+        params: &Arc<Mutex<Vec<u64>>>,
+    ) -> PersistentConfigurationMock {
+        self.set_log_retention_params = params.clone();
+        self
+    }
+
+    pub fn set_log_retention_result(self, result: Result<(), PersistentConfigError>) -> Self {
+        self.set_log_retention_results.borrow_mut().push(result);
+        self
+    }
+
+    pub fn payment_token_result(
+        self,
+        result: Result<Option<String>, PersistentConfigError>,
+    ) -> Self {
+        self.payment_token_results.borrow_mut().push(result);
+        self
+    }
+
+    pub fn set_payment_token_params(
+        mut self,
+        params: &Arc<Mutex<Vec<String>>>,
+    ) -> PersistentConfigurationMock {
+        self.set_payment_token_params = params.clone();
+        self
+    }
+
+    pub fn set_payment_token_result(self, result: Result<(), PersistentConfigError>) -> Self {
+        self.set_payment_token_results.borrow_mut().push(result);
+        self
+    }
+
+    pub fn db_password_failures_result(
+        self,
+        result: Result<(u64, u64), PersistentConfigError>,
+    ) -> Self {
+        self.db_password_failures_results.borrow_mut().push(result);
+        self
+    }
+
+    pub fn set_db_password_failures_params(
+        mut self,
+        params: &Arc<Mutex<Vec<(u64, u64)>>>,
+    ) -> PersistentConfigurationMock {
+        self.set_db_password_failures_params = params.clone();
+        self
+    }
+
+    pub fn set_db_password_failures_result(
+        self,
+        result: Result<(), PersistentConfigError>,
+    ) -> Self {
+        self.set_db_password_failures_results
+            .borrow_mut()
+            .push(result);
+        self
+    }
+
     fn result_from<T: Clone>(results: &RefCell<Vec<T>>) -> T {
         let mut borrowed = results.borrow_mut();
         if borrowed.is_empty() {
@@ -6,7 +6,10 @@ use crate::blockchain::blockchain_interface::{BlockchainError, BlockchainResult,
 use crate::daemon::crash_notification::CrashNotification;
 use crate::daemon::DaemonBindMessage;
 use crate::neighborhood::gossip::Gossip_0v1;
+use crate::proxy_server::udp_relay::InboundClientDatagram;
 use crate::stream_messages::{AddStreamMsg, PoolBindMessage, RemoveStreamMsg};
+use crate::sub_lib::accountant::PaymentOverdue_0v1;
+use crate::sub_lib::accountant::ReportEarningWalletChangeMessage;
 use crate::sub_lib::accountant::ReportExitServiceConsumedMessage;
 use crate::sub_lib::accountant::ReportExitServiceProvidedMessage;
 use crate::sub_lib::accountant::ReportRoutingServiceConsumedMessage;
@@ -27,17 +30,25 @@ use crate::sub_lib::neighborhood::NodeRecordMetadataMessage;
 use crate::sub_lib::neighborhood::RemoveNeighborMessage;
 use crate::sub_lib::neighborhood::RouteQueryMessage;
 use crate::sub_lib::neighborhood::RouteQueryResponse;
-use crate::sub_lib::neighborhood::{DispatcherNodeQueryMessage, GossipFailure_0v1};
+use crate::sub_lib::neighborhood::{
+    Benchmark_0v1, ConnectionAuditMessage, DispatcherNodeQueryMessage, GossipFailure_0v1,
+    HolePunch_0v1, HopTimingsMessage,
+};
 use crate::sub_lib::peer_actors::PeerActors;
 use crate::sub_lib::peer_actors::{BindMessage, StartMessage};
 use crate::sub_lib::proxy_client::{ClientResponsePayload_0v1, InboundServerData};
+use crate::sub_lib::proxy_client::{
+    ClientUdpResponsePayload_0v1, InboundServerDatagram, StreamsQuery,
+};
 use crate::sub_lib::proxy_client::{DnsResolveFailure_0v1, ProxyClientSubs};
 use crate::sub_lib::proxy_server::ProxyServerSubs;
 use crate::sub_lib::proxy_server::{
-    AddReturnRouteMessage, AddRouteMessage, ClientRequestPayload_0v1,
+    AddReturnRouteMessage, AddRouteMessage, ClientRequestPayload_0v1, ClientUdpRequestPayload_0v1,
+    StreamAckPayload_0v1,
 };
 use crate::sub_lib::set_consuming_wallet_message::SetConsumingWalletMessage;
 use crate::sub_lib::stream_handler_pool::DispatcherNodeQueryResponse;
+use crate::sub_lib::stream_handler_pool::PunchHoleMsg;
 use crate::sub_lib::stream_handler_pool::TransmitDataMsg;
 use crate::sub_lib::ui_gateway::UiGatewaySubs;
 use crate::test_utils::to_millis;
@@ -92,41 +103,55 @@ recorder_message_handler!(AddReturnRouteMessage);
 recorder_message_handler!(AddRouteMessage);
 recorder_message_handler!(AddStreamMsg);
 recorder_message_handler!(BindMessage);
+recorder_message_handler!(ConnectionAuditMessage);
 recorder_message_handler!(CrashNotification);
 recorder_message_handler!(DaemonBindMessage);
 recorder_message_handler!(DispatcherNodeQueryMessage);
 recorder_message_handler!(DispatcherNodeQueryResponse);
 recorder_message_handler!(DnsResolveFailure_0v1);
+recorder_message_handler!(ExpiredCoresPackage<Benchmark_0v1>);
 recorder_message_handler!(ExpiredCoresPackage<ClientRequestPayload_0v1>);
 recorder_message_handler!(ExpiredCoresPackage<ClientResponsePayload_0v1>);
+recorder_message_handler!(ExpiredCoresPackage<ClientUdpRequestPayload_0v1>);
+recorder_message_handler!(ExpiredCoresPackage<ClientUdpResponsePayload_0v1>);
 recorder_message_handler!(ExpiredCoresPackage<DnsResolveFailure_0v1>);
 recorder_message_handler!(ExpiredCoresPackage<Gossip_0v1>);
 recorder_message_handler!(ExpiredCoresPackage<GossipFailure_0v1>);
+recorder_message_handler!(ExpiredCoresPackage<HolePunch_0v1>);
 recorder_message_handler!(ExpiredCoresPackage<MessageType>);
+recorder_message_handler!(ExpiredCoresPackage<PaymentOverdue_0v1>);
+recorder_message_handler!(ExpiredCoresPackage<StreamAckPayload_0v1>);
 recorder_message_handler!(GetFinancialStatisticsMessage);
+recorder_message_handler!(HopTimingsMessage);
 recorder_message_handler!(InboundClientData);
 recorder_message_handler!(InboundServerData);
+recorder_message_handler!(InboundServerDatagram);
+recorder_message_handler!(InboundClientDatagram);
 recorder_message_handler!(IncipientCoresPackage);
 recorder_message_handler!(NeighborhoodDotGraphRequest);
 recorder_message_handler!(NodeFromUiMessage);
 recorder_message_handler!(NodeToUiMessage);
 recorder_message_handler!(NodeRecordMetadataMessage);
 recorder_message_handler!(NoLookupIncipientCoresPackage);
+recorder_message_handler!(PaymentOverdue_0v1);
 recorder_message_handler!(PoolBindMessage);
 recorder_message_handler!(ReceivedPayments);
 recorder_message_handler!(RemoveNeighborMessage);
+recorder_message_handler!(StreamsQuery);
 recorder_message_handler!(RemoveStreamMsg);
 recorder_message_handler!(ReportExitServiceConsumedMessage);
 recorder_message_handler!(ReportExitServiceProvidedMessage);
 recorder_message_handler!(ReportRoutingServiceConsumedMessage);
 recorder_message_handler!(ReportRoutingServiceProvidedMessage);
 recorder_message_handler!(SentPayments);
+recorder_message_handler!(ReportEarningWalletChangeMessage);
 recorder_message_handler!(SetConsumingWalletMessage);
 recorder_message_handler!(SetDbPasswordMsg);
 recorder_message_handler!(SetGasPriceMsg);
 recorder_message_handler!(StartMessage);
 recorder_message_handler!(StreamShutdownMsg);
 recorder_message_handler!(TransmitDataMsg);
+recorder_message_handler!(PunchHoleMsg);
 
 impl Handler<NodeQueryMessage> for Recorder {
     type Result = MessageResult<NodeQueryMessage>;
@@ -337,10 +362,14 @@ pub fn make_proxy_server_subs_from(addr: &Addr<Recorder>) -> ProxyServerSubs {
         dns_failure_from_hopper: addr
             .clone()
             .recipient::<ExpiredCoresPackage<DnsResolveFailure_0v1>>(),
+        udp_from_hopper: addr
+            .clone()
+            .recipient::<ExpiredCoresPackage<ClientUdpResponsePayload_0v1>>(),
         add_return_route: recipient!(addr, AddReturnRouteMessage),
         add_route: recipient!(addr, AddRouteMessage),
         stream_shutdown_sub: recipient!(addr, StreamShutdownMsg),
         set_consuming_wallet_sub: recipient!(addr, SetConsumingWalletMessage),
+        from_ui_message_sub: recipient!(addr, NodeFromUiMessage),
     }
 }
 
@@ -351,6 +380,7 @@ pub fn make_dispatcher_subs_from(addr: &Addr<Recorder>) -> DispatcherSubs {
         from_dispatcher_client: recipient!(addr, TransmitDataMsg),
         stream_shutdown_sub: recipient!(addr, StreamShutdownMsg),
         ui_sub: recipient!(addr, NodeFromUiMessage),
+        punch_hole_sub: recipient!(addr, PunchHoleMsg),
     }
 }
 
@@ -371,6 +401,15 @@ pub fn make_proxy_client_subs_from(addr: &Addr<Recorder>) -> ProxyClientSubs {
             .recipient::<ExpiredCoresPackage<ClientRequestPayload_0v1>>(),
         inbound_server_data: recipient!(addr, InboundServerData),
         dns_resolve_failed: recipient!(addr, DnsResolveFailure_0v1),
+        udp_from_hopper: addr
+            .clone()
+            .recipient::<ExpiredCoresPackage<ClientUdpRequestPayload_0v1>>(),
+        inbound_server_datagram: recipient!(addr, InboundServerDatagram),
+        stream_ack_from_hopper: addr
+            .clone()
+            .recipient::<ExpiredCoresPackage<StreamAckPayload_0v1>>(),
+        streams_query: recipient!(addr, StreamsQuery),
+        payment_overdue: recipient!(addr, PaymentOverdue_0v1),
     }
 }
 
@@ -385,6 +424,14 @@ pub fn make_neighborhood_subs_from(addr: &Addr<Recorder>) -> NeighborhoodSubs {
         gossip_failure: addr
             .clone()
             .recipient::<ExpiredCoresPackage<GossipFailure_0v1>>(),
+        hole_punch: addr
+            .clone()
+            .recipient::<ExpiredCoresPackage<HolePunch_0v1>>(),
+        benchmark: addr
+            .clone()
+            .recipient::<ExpiredCoresPackage<Benchmark_0v1>>(),
+        hop_timings: recipient!(addr, HopTimingsMessage),
+        connection_audit: recipient!(addr, ConnectionAuditMessage),
         dispatcher_node_query: recipient!(addr, DispatcherNodeQueryMessage),
         remove_neighbor: recipient!(addr, RemoveNeighborMessage),
         stream_shutdown_sub: recipient!(addr, StreamShutdownMsg),
@@ -407,6 +454,10 @@ pub fn make_accountant_subs_from(addr: &Addr<Recorder>) -> AccountantSubs {
         report_exit_service_consumed: recipient!(addr, ReportExitServiceConsumedMessage),
         report_new_payments: recipient!(addr, ReceivedPayments),
         report_sent_payments: recipient!(addr, SentPayments),
+        report_earning_wallet_change: recipient!(addr, ReportEarningWalletChangeMessage),
+        payment_overdue_from_hopper: addr
+            .clone()
+            .recipient::<ExpiredCoresPackage<PaymentOverdue_0v1>>(),
         ui_message_sub: recipient!(addr, NodeFromUiMessage),
     }
 }
@@ -0,0 +1,114 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use crate::sub_lib::clock::Clock;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+// Timers shorter than this would keep an actor too busy to do anything else
+const MINIMUM_REAL_INTERVAL: Duration = Duration::from_millis(1);
+
+// A Clock that starts at whatever time it's given, then either stands still until it's advanced or
+// runs some number of times faster than real time. Clones share the same time, so a test can keep
+// one and move the time out from under the actors it gave the others to.
+#[derive(Clone, Debug)]
+pub struct VirtualClock {
+    time: Arc<Mutex<VirtualTime>>,
+}
+
+#[derive(Debug)]
+struct VirtualTime {
+    at: SystemTime,
+    since: Instant,
+    speedup: u32,
+}
+
+impl VirtualTime {
+    fn now(&self) -> SystemTime {
+        self.at + self.since.elapsed() * self.speedup
+    }
+}
+
+impl Clock for VirtualClock {
+    fn now(&self) -> SystemTime {
+        self.time.lock().expect("VirtualClock is poisoned").now()
+    }
+
+    fn real_interval(&self, interval: Duration) -> Duration {
+        match self.time.lock().expect("VirtualClock is poisoned").speedup {
+            0 => interval,
+            speedup => (interval / speedup).max(MINIMUM_REAL_INTERVAL),
+        }
+    }
+}
+
+impl VirtualClock {
+    pub fn new(start: SystemTime) -> Self {
+        Self::running(start, 0)
+    }
+
+    pub fn running(start: SystemTime, speedup: u32) -> Self {
+        Self {
+            time: Arc::new(Mutex::new(VirtualTime {
+                at: start,
+                since: Instant::now(),
+                speedup,
+            })),
+        }
+    }
+
+    pub fn advance(&self, by: Duration) {
+        let mut time = self.time.lock().expect("VirtualClock is poisoned");
+        time.at = time.now() + by;
+        time.since = Instant::now();
+    }
+
+    pub fn set(&self, to: SystemTime) {
+        let mut time = self.time.lock().expect("VirtualClock is poisoned");
+        time.at = to;
+        time.since = Instant::now();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn a_stopped_clock_moves_only_when_it_is_advanced_and_its_clones_move_with_it() {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let subject = VirtualClock::new(start);
+        let clone = subject.clone();
+
+        thread::sleep(Duration::from_millis(10));
+        let before = clone.now();
+        subject.advance(Duration::from_secs(7 * 24 * 3600));
+        let after = clone.now();
+        subject.set(start);
+
+        assert_eq!(before, start);
+        assert_eq!(after, start + Duration::from_secs(7 * 24 * 3600));
+        assert_eq!(clone.now(), start);
+        assert_eq!(
+            subject.real_interval(Duration::from_secs(60)),
+            Duration::from_secs(60)
+        );
+    }
+
+    #[test]
+    fn a_running_clock_runs_faster_than_real_time_and_so_do_its_timers() {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let subject = VirtualClock::running(start, 1000);
+
+        thread::sleep(Duration::from_millis(10));
+
+        assert!(subject.now() >= start + Duration::from_secs(10));
+        assert_eq!(
+            subject.real_interval(Duration::from_secs(60)),
+            Duration::from_millis(60)
+        );
+        assert_eq!(
+            subject.real_interval(Duration::from_micros(10)),
+            MINIMUM_REAL_INTERVAL
+        );
+    }
+}
@@ -0,0 +1,308 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use crate::database::connection_wrapper::ConnectionWrapper;
+use crate::database::dao_utils::DaoFactoryReal;
+use crate::sub_lib::wallet::Wallet;
+use lazy_static::lazy_static;
+use rusqlite::{Row, ToSql, NO_PARAMS};
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+// What a wallet nobody knows anything about scores
+pub const NEUTRAL_TRUST_SCORE: u8 = 50;
+// Uptime counts for half of what it can once a neighbor has been connected this long
+const UPTIME_HALF_TRUST_SECONDS: u64 = 24 * 60 * 60;
+
+lazy_static! {
+    pub static ref TRUST_CACHE: TrustCache = TrustCache::default();
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TrustEvidence {
+    PaidOnTime,
+    PaymentOverdue,
+    AccurateGossip,
+    InaccurateGossip,
+    Uptime(u64),
+}
+
+// Everything a Node has seen of the neighbors that earn into one wallet. Trust follows the wallet
+// that gets paid, so an operator can't shed a bad record by running under a new key.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TrustRecord {
+    pub payments_on_time: u64,
+    pub payments_overdue: u64,
+    pub accurate_gossip: u64,
+    pub inaccurate_gossip: u64,
+    pub uptime_seconds: u64,
+}
+
+impl TrustRecord {
+    // From 0 to 100. Each kind of evidence starts out at a half, so a wallet with no record scores
+    // NEUTRAL_TRUST_SCORE; overdue payments and inaccurate Gossip weigh more than their opposites.
+    pub fn score(&self) -> u8 {
+        let payment = (self.payments_on_time + 1) as f64
+            / (self.payments_on_time + 3 * self.payments_overdue + 2) as f64;
+        let gossip = (self.accurate_gossip + 1) as f64
+            / (self.accurate_gossip + 5 * self.inaccurate_gossip + 2) as f64;
+        let uptime = (self.uptime_seconds + UPTIME_HALF_TRUST_SECONDS) as f64
+            / (self.uptime_seconds + 2 * UPTIME_HALF_TRUST_SECONDS) as f64;
+        (100.0 * (0.5 * payment + 0.3 * gossip + 0.2 * uptime)).round() as u8
+    }
+
+    fn add(&mut self, evidence: TrustEvidence) {
+        match evidence {
+            TrustEvidence::PaidOnTime => self.payments_on_time += 1,
+            TrustEvidence::PaymentOverdue => self.payments_overdue += 1,
+            TrustEvidence::AccurateGossip => self.accurate_gossip += 1,
+            TrustEvidence::InaccurateGossip => self.inaccurate_gossip += 1,
+            TrustEvidence::Uptime(seconds) => self.uptime_seconds += seconds,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct TrustCache {
+    cache: RwLock<HashMap<Wallet, TrustRecord>>,
+}
+
+impl TrustCache {
+    pub fn insert(&self, wallet: &Wallet, record: TrustRecord) {
+        self.cache
+            .write()
+            .expect("Failed to insert trust into cache")
+            .insert(wallet.as_address_wallet(), record);
+    }
+
+    pub fn record(&self, wallet: &Wallet) -> Option<TrustRecord> {
+        self.cache
+            .read()
+            .expect("Failed to read from trust cache")
+            .get(&wallet.as_address_wallet())
+            .cloned()
+    }
+
+    pub fn records(&self) -> Vec<(Wallet, TrustRecord)> {
+        self.cache
+            .read()
+            .expect("Failed to read from trust cache")
+            .iter()
+            .map(|(wallet, record)| (wallet.clone(), *record))
+            .collect()
+    }
+
+    pub fn score(&self, wallet: &Wallet) -> u8 {
+        self.record(wallet)
+            .map(|record| record.score())
+            .unwrap_or(NEUTRAL_TRUST_SCORE)
+    }
+}
+
+pub trait TrustCacheLoader {
+    fn load(&self, conn: Box<dyn ConnectionWrapper>);
+}
+
+pub struct TrustCacheLoaderReal {}
+
+impl TrustCacheLoader for TrustCacheLoaderReal {
+    fn load(&self, conn: Box<dyn ConnectionWrapper>) {
+        TrustDaoReal::new(conn)
+            .trust_list()
+            .into_iter()
+            .for_each(|(wallet, record)| TRUST_CACHE.insert(&wallet, record));
+    }
+}
+
+pub trait TrustDao: Send {
+    fn trust_list(&self) -> Vec<(Wallet, TrustRecord)>;
+    // Also brings TRUST_CACHE up to date
+    fn add_evidence(&self, wallet: &Wallet, evidence: TrustEvidence);
+}
+
+pub trait TrustDaoFactory {
+    fn make(&self) -> Box<dyn TrustDao>;
+}
+
+impl TrustDaoFactory for DaoFactoryReal {
+    fn make(&self) -> Box<dyn TrustDao> {
+        Box::new(TrustDaoReal::new(self.make_connection()))
+    }
+}
+
+pub struct TrustDaoReal {
+    conn: Box<dyn ConnectionWrapper>,
+}
+
+impl TrustDao for TrustDaoReal {
+    fn trust_list(&self) -> Vec<(Wallet, TrustRecord)> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "select wallet_address, payments_on_time, payments_overdue, accurate_gossip, \
+                 inaccurate_gossip, uptime_seconds from trust",
+            )
+            .expect("Internal error");
+        stmt.query_map(NO_PARAMS, Self::row_to_record)
+            .expect("Couldn't retrieve trust list: database corrupt")
+            .map(|row| row.unwrap_or_else(|e| panic!("Database is corrupt: {:?}", e)))
+            .collect()
+    }
+
+    fn add_evidence(&self, wallet: &Wallet, evidence: TrustEvidence) {
+        let mut added = TrustRecord::default();
+        added.add(evidence);
+        let wallet = wallet.as_address_wallet();
+        let params: &[&dyn ToSql] = &[
+            &wallet,
+            &(added.payments_on_time as i64),
+            &(added.payments_overdue as i64),
+            &(added.accurate_gossip as i64),
+            &(added.inaccurate_gossip as i64),
+            &(added.uptime_seconds as i64),
+        ];
+        self.conn
+            .prepare(
+                "insert into trust (wallet_address, payments_on_time, payments_overdue, \
+                 accurate_gossip, inaccurate_gossip, uptime_seconds) values (?, ?, ?, ?, ?, ?) \
+                 on conflict (wallet_address) do update set \
+                 payments_on_time = payments_on_time + excluded.payments_on_time, \
+                 payments_overdue = payments_overdue + excluded.payments_overdue, \
+                 accurate_gossip = accurate_gossip + excluded.accurate_gossip, \
+                 inaccurate_gossip = inaccurate_gossip + excluded.inaccurate_gossip, \
+                 uptime_seconds = uptime_seconds + excluded.uptime_seconds",
+            )
+            .expect("Internal error")
+            .execute(params)
+            .unwrap_or_else(|e| {
+                panic!(
+                    "Could not record trust evidence for {} because of database corruption: {}",
+                    wallet, e
+                )
+            });
+        let params: &[&dyn ToSql] = &[&wallet];
+        let (_, record) = self
+            .conn
+            .prepare(
+                "select wallet_address, payments_on_time, payments_overdue, accurate_gossip, \
+                 inaccurate_gossip, uptime_seconds from trust where wallet_address = ?",
+            )
+            .expect("Internal error")
+            .query_row(params, Self::row_to_record)
+            .unwrap_or_else(|e| panic!("Database is corrupt: {:?}", e));
+        TRUST_CACHE.insert(&wallet, record);
+    }
+}
+
+impl TrustDaoReal {
+    pub fn new(conn: Box<dyn ConnectionWrapper>) -> Self {
+        Self { conn }
+    }
+
+    fn row_to_record(row: &Row) -> rusqlite::Result<(Wallet, TrustRecord)> {
+        let wallet: Wallet = row.get(0)?;
+        Ok((
+            wallet,
+            TrustRecord {
+                payments_on_time: row.get::<usize, i64>(1)? as u64,
+                payments_overdue: row.get::<usize, i64>(2)? as u64,
+                accurate_gossip: row.get::<usize, i64>(3)? as u64,
+                inaccurate_gossip: row.get::<usize, i64>(4)? as u64,
+                uptime_seconds: row.get::<usize, i64>(5)? as u64,
+            },
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::db_initializer::{DbInitializer, DbInitializerReal};
+    use crate::test_utils::make_wallet;
+    use masq_lib::test_utils::utils::{ensure_node_home_directory_exists, DEFAULT_CHAIN_ID};
+
+    #[test]
+    fn scores_start_out_neutral_and_move_with_the_evidence() {
+        let nobody = TrustRecord::default();
+        let good = TrustRecord {
+            payments_on_time: 20,
+            payments_overdue: 0,
+            accurate_gossip: 100,
+            inaccurate_gossip: 0,
+            uptime_seconds: 30 * UPTIME_HALF_TRUST_SECONDS,
+        };
+        let bad = TrustRecord {
+            payments_overdue: 3,
+            inaccurate_gossip: 2,
+            ..TrustRecord::default()
+        };
+
+        assert_eq!(nobody.score(), NEUTRAL_TRUST_SCORE);
+        assert_eq!(good.score(), 97);
+        assert_eq!(bad.score(), 17);
+        assert_eq!(
+            TRUST_CACHE.score(&make_wallet("scores_start_out_neutral")),
+            NEUTRAL_TRUST_SCORE
+        );
+    }
+
+    #[test]
+    fn evidence_accumulates_in_the_database_and_the_cache() {
+        let home_dir = ensure_node_home_directory_exists(
+            "trust_dao",
+            "evidence_accumulates_in_the_database_and_the_cache",
+        );
+        let db_initializer = DbInitializerReal::new();
+        let subject = TrustDaoReal::new(
+            db_initializer
+                .initialize(&home_dir, DEFAULT_CHAIN_ID, true)
+                .unwrap(),
+        );
+        let wallet = make_wallet("evidence_accumulates");
+
+        subject.add_evidence(&wallet, TrustEvidence::PaidOnTime);
+        subject.add_evidence(&wallet, TrustEvidence::Uptime(3600));
+        subject.add_evidence(&wallet, TrustEvidence::Uptime(1800));
+        subject.add_evidence(&wallet, TrustEvidence::InaccurateGossip);
+
+        let expected = TrustRecord {
+            payments_on_time: 1,
+            payments_overdue: 0,
+            accurate_gossip: 0,
+            inaccurate_gossip: 1,
+            uptime_seconds: 5400,
+        };
+        assert_eq!(subject.trust_list(), vec![(wallet.clone(), expected)]);
+        assert_eq!(TRUST_CACHE.record(&wallet), Some(expected));
+        assert_eq!(TRUST_CACHE.score(&wallet), expected.score());
+    }
+
+    #[test]
+    fn the_cache_loader_brings_back_what_the_database_remembers() {
+        let home_dir = ensure_node_home_directory_exists(
+            "trust_dao",
+            "the_cache_loader_brings_back_what_the_database_remembers",
+        );
+        let db_initializer = DbInitializerReal::new();
+        let conn = db_initializer
+            .initialize(&home_dir, DEFAULT_CHAIN_ID, true)
+            .unwrap();
+        let wallet = make_wallet("remembered");
+        let params: &[&dyn ToSql] = &[&wallet];
+        conn.prepare("insert into trust values (?, 1, 2, 3, 4, 5)")
+            .unwrap()
+            .execute(params)
+            .unwrap();
+
+        TrustCacheLoaderReal {}.load(conn);
+
+        assert_eq!(
+            TRUST_CACHE.record(&wallet),
+            Some(TrustRecord {
+                payments_on_time: 1,
+                payments_overdue: 2,
+                accurate_gossip: 3,
+                inaccurate_gossip: 4,
+                uptime_seconds: 5,
+            })
+        );
+    }
+}
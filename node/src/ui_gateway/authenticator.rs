@@ -0,0 +1,307 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use crate::sub_lib::ui_gateway::UiAuthConfig;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+pub const MAX_AUTHENTICATION_FAILURES: u32 = 5;
+pub const AUTHENTICATION_LOCKOUT: Duration = Duration::from_secs(300);
+
+// Opcodes that only look at the Node; everything else can change it
+const READ_ONLY_OPCODES: &[&str] = &[
+    "applicationRules",
+    "balanceHistory",
+    "configurationSchema",
+    "connectionAudit",
+    "dataCap",
+    "descriptor",
+    "dnsStatus",
+    "financials",
+    "geoDatabase",
+    "health",
+    "instances",
+    "neighborhoodGraph",
+    "routeQuote",
+    "splitTunnelRules",
+    "streams",
+    "subscribe",
+    "unsubscribe",
+    "usageHistory",
+    "walletBalances",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UiPermission {
+    ReadOnly,
+    Full,
+}
+
+impl UiPermission {
+    pub fn allows(self, opcode: &str) -> bool {
+        match self {
+            UiPermission::Full => true,
+            UiPermission::ReadOnly => READ_ONLY_OPCODES.contains(&opcode),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            UiPermission::ReadOnly => "readOnly",
+            UiPermission::Full => "full",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AuthenticationFailure {
+    WrongPassword { attempts_left: u32 },
+    LockedOut { seconds_left: u64 },
+}
+
+#[derive(Default)]
+struct FailureRecord {
+    failures: u32,
+    locked_until_opt: Option<Instant>,
+}
+
+// Failures are counted per IP address rather than per connection, since reconnecting costs an
+// attacker nothing.
+pub struct UiAuthenticator {
+    config_opt: Option<UiAuthConfig>,
+    failures_by_ip: HashMap<IpAddr, FailureRecord>,
+}
+
+impl UiAuthenticator {
+    pub fn new(config_opt: Option<UiAuthConfig>) -> UiAuthenticator {
+        UiAuthenticator {
+            config_opt,
+            failures_by_ip: HashMap::new(),
+        }
+    }
+
+    // What a connection may do before it authenticates: anything at all if no passwords are set
+    pub fn initial_permission(&self) -> Option<UiPermission> {
+        match self.config_opt {
+            None => Some(UiPermission::Full),
+            Some(_) => None,
+        }
+    }
+
+    pub fn authenticate(
+        &mut self,
+        ip_addr: IpAddr,
+        password: &str,
+        now: Instant,
+    ) -> Result<UiPermission, AuthenticationFailure> {
+        let config = match &self.config_opt {
+            None => return Ok(UiPermission::Full),
+            Some(config) => config,
+        };
+        if let Some(locked_until) = self
+            .failures_by_ip
+            .get(&ip_addr)
+            .and_then(|record| record.locked_until_opt)
+        {
+            if now < locked_until {
+                return Err(AuthenticationFailure::LockedOut {
+                    seconds_left: seconds_rounded_up(locked_until - now),
+                });
+            }
+            self.failures_by_ip.remove(&ip_addr);
+        }
+        let permission_opt = if same_password(password, &config.full_password) {
+            Some(UiPermission::Full)
+        } else {
+            match &config.read_only_password_opt {
+                Some(read_only_password) if same_password(password, read_only_password) => {
+                    Some(UiPermission::ReadOnly)
+                }
+                _ => None,
+            }
+        };
+        match permission_opt {
+            Some(permission) => {
+                self.failures_by_ip.remove(&ip_addr);
+                Ok(permission)
+            }
+            None => {
+                let record = self.failures_by_ip.entry(ip_addr).or_default();
+                record.failures += 1;
+                if record.failures >= MAX_AUTHENTICATION_FAILURES {
+                    record.locked_until_opt = Some(now + AUTHENTICATION_LOCKOUT);
+                    Err(AuthenticationFailure::LockedOut {
+                        seconds_left: AUTHENTICATION_LOCKOUT.as_secs(),
+                    })
+                } else {
+                    Err(AuthenticationFailure::WrongPassword {
+                        attempts_left: MAX_AUTHENTICATION_FAILURES - record.failures,
+                    })
+                }
+            }
+        }
+    }
+}
+
+// Looks at every byte even after a mismatch, so that how long it takes says nothing about how
+// much of a guess was right
+fn same_password(candidate: &str, password: &str) -> bool {
+    let (candidate, password) = (candidate.as_bytes(), password.as_bytes());
+    candidate.len() == password.len()
+        && candidate
+            .iter()
+            .zip(password)
+            .fold(0u8, |difference, (a, b)| difference | (a ^ b))
+            == 0
+}
+
+fn seconds_rounded_up(duration: Duration) -> u64 {
+    duration.as_secs() + if duration.subsec_nanos() > 0 { 1 } else { 0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+    use std::str::FromStr;
+
+    // Everything a UI can ask for that read-only UIs may not. authenticate is answered before
+    // permission comes into it.
+    const FULL_ONLY_OPCODES: &[&str] = &[
+        "authenticate",
+        "collectDiagnostics",
+        "dnsRevert",
+        "dnsSubvert",
+        "effectiveConfig",
+        "encryptedSniHost",
+        "geoDatabaseRefresh",
+        "logLevel",
+        "requestTestFunds",
+        "rotateLog",
+        "setApplicationRules",
+        "setSplitTunnelRules",
+        "setup",
+        "shutdown",
+        "speedTest",
+        "start",
+    ];
+
+    fn make_subject() -> UiAuthenticator {
+        UiAuthenticator::new(Some(UiAuthConfig {
+            full_password: "booga".to_string(),
+            read_only_password_opt: Some("agoob".to_string()),
+        }))
+    }
+
+    #[test]
+    fn without_passwords_everybody_has_full_access() {
+        let mut subject = UiAuthenticator::new(None);
+
+        let initial_permission = subject.initial_permission();
+        let result = subject.authenticate(
+            IpAddr::from_str("1.2.3.4").unwrap(),
+            "whatever",
+            Instant::now(),
+        );
+
+        assert_eq!(initial_permission, Some(UiPermission::Full));
+        assert_eq!(result, Ok(UiPermission::Full));
+    }
+
+    #[test]
+    fn each_password_grants_its_own_permission() {
+        let mut subject = make_subject();
+        let ip_addr = IpAddr::from_str("1.2.3.4").unwrap();
+        let now = Instant::now();
+
+        let initial_permission = subject.initial_permission();
+        let full = subject.authenticate(ip_addr, "booga", now);
+        let read_only = subject.authenticate(ip_addr, "agoob", now);
+        let wrong = subject.authenticate(ip_addr, "boog", now);
+
+        assert_eq!(initial_permission, None);
+        assert_eq!(full, Ok(UiPermission::Full));
+        assert_eq!(read_only, Ok(UiPermission::ReadOnly));
+        assert_eq!(
+            wrong,
+            Err(AuthenticationFailure::WrongPassword {
+                attempts_left: MAX_AUTHENTICATION_FAILURES - 1
+            })
+        );
+    }
+
+    #[test]
+    fn too_many_failures_lock_out_the_address_even_with_the_right_password() {
+        let mut subject = make_subject();
+        let ip_addr = IpAddr::from_str("1.2.3.4").unwrap();
+        let now = Instant::now();
+        let failures = (0..MAX_AUTHENTICATION_FAILURES)
+            .map(|_| subject.authenticate(ip_addr, "wrong", now))
+            .collect::<Vec<_>>();
+
+        let during_lockout =
+            subject.authenticate(ip_addr, "booga", now + Duration::from_millis(59_500));
+        let other_address =
+            subject.authenticate(IpAddr::from_str("5.6.7.8").unwrap(), "booga", now);
+        let after_lockout = subject.authenticate(ip_addr, "booga", now + AUTHENTICATION_LOCKOUT);
+
+        assert_eq!(
+            failures.last().unwrap(),
+            &Err(AuthenticationFailure::LockedOut {
+                seconds_left: AUTHENTICATION_LOCKOUT.as_secs()
+            })
+        );
+        assert_eq!(
+            during_lockout,
+            Err(AuthenticationFailure::LockedOut {
+                seconds_left: AUTHENTICATION_LOCKOUT.as_secs() - 59
+            })
+        );
+        assert_eq!(other_address, Ok(UiPermission::Full));
+        assert_eq!(after_lockout, Ok(UiPermission::Full));
+    }
+
+    #[test]
+    fn read_only_permission_allows_only_looking() {
+        assert_eq!(UiPermission::ReadOnly.allows("financials"), true);
+        assert_eq!(UiPermission::ReadOnly.allows("usageHistory"), true);
+        assert_eq!(UiPermission::ReadOnly.allows("dnsStatus"), true);
+        assert_eq!(UiPermission::ReadOnly.allows("connectionAudit"), true);
+        assert_eq!(UiPermission::ReadOnly.allows("balanceHistory"), true);
+        assert_eq!(UiPermission::ReadOnly.allows("setup"), false);
+        assert_eq!(UiPermission::ReadOnly.allows("shutdown"), false);
+        assert_eq!(UiPermission::Full.allows("shutdown"), true);
+    }
+
+    #[test]
+    fn every_conversational_opcode_is_either_read_only_or_full_only() {
+        let messages = include_str!("../../../masq_lib/src/messages.rs");
+        let opcodes = messages
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix("conversation_message!("))
+            .filter_map(|rest| rest.split('"').nth(1))
+            .collect::<BTreeSet<&str>>();
+        let classified = READ_ONLY_OPCODES
+            .iter()
+            .chain(FULL_ONLY_OPCODES.iter())
+            .cloned()
+            .collect::<BTreeSet<&str>>();
+
+        assert_eq!(
+            opcodes.difference(&classified).collect::<Vec<_>>(),
+            Vec::<&&str>::new(),
+            "Add these opcodes to READ_ONLY_OPCODES or FULL_ONLY_OPCODES"
+        );
+        assert_eq!(
+            classified.difference(&opcodes).collect::<Vec<_>>(),
+            Vec::<&&str>::new(),
+            "These opcodes are no longer conversational"
+        );
+        assert_eq!(
+            READ_ONLY_OPCODES
+                .iter()
+                .filter(|opcode| FULL_ONLY_OPCODES.contains(opcode))
+                .collect::<Vec<_>>(),
+            Vec::<&&str>::new()
+        );
+    }
+}
@@ -0,0 +1,577 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use crate::sub_lib::logger::Logger;
+use crate::sub_lib::ui_gateway::UiAuthConfig;
+use crate::ui_gateway::authenticator::{AuthenticationFailure, UiAuthenticator};
+use crate::ui_gateway::grpc_schema::{
+    frame, json_from_protobuf, protobuf_from_json, unframe, GrpcMethod,
+};
+use actix::Recipient;
+use bytes::Bytes;
+use futures::sync::oneshot;
+use h2::server::SendResponse;
+use h2::RecvStream;
+use http::{HeaderMap, HeaderValue, Request, Response};
+use masq_lib::ui_gateway::MessagePath::Conversation;
+use masq_lib::ui_gateway::MessageTarget::ClientId;
+use masq_lib::ui_gateway::{MessageBody, NodeFromUiMessage, NodeToUiMessage};
+use masq_lib::utils::localhost;
+use std::collections::HashMap;
+use std::io;
+use std::net::{IpAddr, SocketAddr, TcpListener};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc;
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use tokio::prelude::{Future, Stream};
+use tokio::reactor::Handle;
+
+// Above the REST facade's client IDs, which are above the WebSocket clients'
+pub const GRPC_CLIENT_ID_BASE: u64 = (1 << 63) + (1 << 62);
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(30);
+
+// https://github.com/grpc/grpc/blob/master/doc/statuscodes.md
+const GRPC_OK: u32 = 0;
+const GRPC_UNKNOWN: u32 = 2;
+const GRPC_INVALID_ARGUMENT: u32 = 3;
+const GRPC_DEADLINE_EXCEEDED: u32 = 4;
+const GRPC_PERMISSION_DENIED: u32 = 7;
+const GRPC_RESOURCE_EXHAUSTED: u32 = 8;
+const GRPC_UNIMPLEMENTED: u32 = 12;
+const GRPC_INTERNAL: u32 = 13;
+const GRPC_UNAUTHENTICATED: u32 = 16;
+
+struct GrpcCall {
+    path: String,
+    authorization_opt: Option<String>,
+    data: Vec<u8>,
+}
+
+#[derive(Debug, PartialEq)]
+struct GrpcReply {
+    status: u32,
+    message: String,
+    // The code the Node's UI error carried, for callers that want to tell its errors apart
+    error_code_opt: Option<u64>,
+    data_opt: Option<Vec<u8>>,
+}
+
+impl GrpcReply {
+    fn error(status: u32, message: &str) -> GrpcReply {
+        GrpcReply {
+            status,
+            message: message.to_string(),
+            error_code_opt: None,
+            data_opt: None,
+        }
+    }
+}
+
+struct GrpcFacadeInner {
+    methods: &'static [&'static GrpcMethod],
+    from_ui_message_sub: Mutex<Recipient<NodeFromUiMessage>>,
+    authenticator: Mutex<UiAuthenticator>,
+    next_client_id: AtomicU64,
+    waiting_by_client_id: Mutex<HashMap<u64, Sender<MessageBody>>>,
+    logger: Logger,
+}
+
+// gRPC on localhost for controllers that would rather have typed stubs than WebSocket JSON. Like
+// the REST facade, each call becomes a conversation with the Node under a client ID of its own,
+// and the UiGateway hands the response back here; the protobuf on either side of it is
+// translated to and from the UI message's JSON by grpc_schema.
+pub struct GrpcFacade {
+    inner: Arc<GrpcFacadeInner>,
+}
+
+impl GrpcFacade {
+    pub fn start(
+        port: u16,
+        methods: &'static [&'static GrpcMethod],
+        auth_opt: Option<UiAuthConfig>,
+        from_ui_message_sub: Recipient<NodeFromUiMessage>,
+    ) -> io::Result<GrpcFacade> {
+        let listener = TcpListener::bind(SocketAddr::new(localhost(), port))?;
+        let inner = Arc::new(GrpcFacadeInner {
+            methods,
+            from_ui_message_sub: Mutex::new(from_ui_message_sub),
+            authenticator: Mutex::new(UiAuthenticator::new(auth_opt)),
+            next_client_id: AtomicU64::new(GRPC_CLIENT_ID_BASE),
+            waiting_by_client_id: Mutex::new(HashMap::new()),
+            logger: Logger::new("GrpcFacade"),
+        });
+        let local_addr = listener.local_addr()?;
+        info!(inner.logger, "Serving the gRPC facade at {}", local_addr);
+        let inner_c = inner.clone();
+        thread::spawn(move || {
+            tokio::run(futures::future::lazy(move || {
+                let listener = tokio::net::TcpListener::from_std(listener, &Handle::default())
+                    .expect("Couldn't hand gRPC listener to tokio");
+                let logger = inner_c.logger.clone();
+                listener
+                    .incoming()
+                    .map_err(move |e| warning!(logger, "Couldn't accept gRPC connection: {}", e))
+                    .for_each(move |stream| {
+                        tokio::spawn(serve_connection(inner_c.clone(), stream));
+                        Ok(())
+                    })
+            }))
+        });
+        Ok(GrpcFacade { inner })
+    }
+
+    // Gives back any message that isn't a response to a gRPC call
+    pub fn claim(&self, msg: NodeToUiMessage) -> Option<NodeToUiMessage> {
+        let client_id = match msg.target {
+            ClientId(client_id) if client_id >= GRPC_CLIENT_ID_BASE => client_id,
+            _ => return Some(msg),
+        };
+        let waiting_opt = self
+            .inner
+            .waiting_by_client_id
+            .lock()
+            .expect("gRPC facade is poisoned")
+            .remove(&client_id);
+        match waiting_opt {
+            // The gRPC client may have given up already; nothing to be done about that
+            Some(waiting) => {
+                let _ = waiting.send(msg.body);
+            }
+            None => debug!(
+                self.inner.logger,
+                "Dropping {} response for departed gRPC client {}", msg.body.opcode, client_id
+            ),
+        }
+        None
+    }
+}
+
+fn serve_connection(
+    inner: Arc<GrpcFacadeInner>,
+    stream: TcpStream,
+) -> Box<dyn Future<Item = (), Error = ()> + Send> {
+    let peer_ip = match stream.peer_addr() {
+        Ok(peer_addr) => peer_addr.ip(),
+        Err(_) => return Box::new(futures::future::ok(())),
+    };
+    let logger = inner.logger.clone();
+    Box::new(
+        h2::server::handshake(stream)
+            .and_then(move |connection| {
+                connection.for_each(move |(request, respond)| {
+                    tokio::spawn(serve_call(inner.clone(), peer_ip, request, respond));
+                    Ok(())
+                })
+            })
+            .map_err(move |e| debug!(logger, "gRPC connection failed: {}", e)),
+    )
+}
+
+fn serve_call(
+    inner: Arc<GrpcFacadeInner>,
+    peer_ip: IpAddr,
+    request: Request<RecvStream>,
+    mut respond: SendResponse<Bytes>,
+) -> impl Future<Item = (), Error = ()> {
+    let (parts, mut body) = request.into_parts();
+    let mut release_capacity = body.release_capacity().clone();
+    let logger = inner.logger.clone();
+    body.map(move |chunk| {
+        let _ = release_capacity.release_capacity(chunk.len());
+        chunk
+    })
+    .concat2()
+    .map_err(|e| GrpcReply::error(GRPC_INTERNAL, &format!("Couldn't read request: {}", e)))
+    .and_then(move |data| {
+        let call = GrpcCall {
+            path: parts.uri.path().to_string(),
+            authorization_opt: parts
+                .headers
+                .get("authorization")
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.to_string()),
+            data: data.to_vec(),
+        };
+        // Answering waits on the Node, which mustn't hold up the other calls
+        let (tx, rx) = oneshot::channel();
+        thread::spawn(move || {
+            let _ = tx.send(answer(&inner, peer_ip, call));
+        });
+        rx.map_err(|_| GrpcReply::error(GRPC_INTERNAL, "Call was abandoned"))
+    })
+    .then(move |result| {
+        let reply = result.unwrap_or_else(|reply| reply);
+        if let Err(e) = send_reply(&mut respond, reply) {
+            debug!(logger, "Couldn't send gRPC reply: {}", e);
+        }
+        Ok::<(), ()>(())
+    })
+}
+
+fn send_reply(respond: &mut SendResponse<Bytes>, reply: GrpcReply) -> Result<(), h2::Error> {
+    let response = Response::builder()
+        .status(200)
+        .header("content-type", "application/grpc")
+        .body(())
+        .expect("Couldn't build gRPC response");
+    let mut stream = respond.send_response(response, false)?;
+    if let Some(data) = reply.data_opt {
+        stream.send_data(Bytes::from(frame(&data)), false)?;
+    }
+    let mut trailers = HeaderMap::new();
+    trailers.insert("grpc-status", HeaderValue::from(reply.status));
+    if !reply.message.is_empty() {
+        trailers.insert(
+            "grpc-message",
+            HeaderValue::from_str(&percent_encode(&reply.message))
+                .expect("Percent-encoding let a bad character through"),
+        );
+    }
+    if let Some(code) = reply.error_code_opt {
+        trailers.insert("masq-error-code", HeaderValue::from(code));
+    }
+    stream.send_trailers(trailers)
+}
+
+fn answer(inner: &GrpcFacadeInner, peer_ip: IpAddr, call: GrpcCall) -> GrpcReply {
+    let method = match inner
+        .methods
+        .iter()
+        .find(|method| method.path() == call.path)
+    {
+        Some(method) => method,
+        None => {
+            return GrpcReply::error(
+                GRPC_UNIMPLEMENTED,
+                &format!("{} isn't served here", call.path),
+            )
+        }
+    };
+    let password = match &call.authorization_opt {
+        Some(authorization) if authorization.starts_with("Bearer ") => &authorization[7..],
+        _ => "",
+    };
+    let authentication = inner
+        .authenticator
+        .lock()
+        .expect("gRPC facade is poisoned")
+        .authenticate(peer_ip, password, Instant::now());
+    match authentication {
+        Ok(permission) if permission.allows(method.opcode) => (),
+        Ok(_) => {
+            return GrpcReply::error(
+                GRPC_PERMISSION_DENIED,
+                &format!("A read-only password can't call {}", method.name),
+            )
+        }
+        Err(AuthenticationFailure::WrongPassword { attempts_left }) => {
+            return GrpcReply::error(
+                GRPC_UNAUTHENTICATED,
+                &format!(
+                    "Send authorization: Bearer <ui-password>; {} more wrong passwords will lock this address out",
+                    attempts_left
+                ),
+            )
+        }
+        Err(AuthenticationFailure::LockedOut { seconds_left }) => {
+            return GrpcReply::error(
+                GRPC_RESOURCE_EXHAUSTED,
+                &format!(
+                    "Too many wrong passwords: locked out for {} more seconds",
+                    seconds_left
+                ),
+            )
+        }
+    }
+    let payload =
+        match unframe(&call.data).and_then(|data| json_from_protobuf(method.request, data)) {
+            Ok(payload) => payload.to_string(),
+            Err(message) => return GrpcReply::error(GRPC_INVALID_ARGUMENT, &message),
+        };
+    converse(inner, method, payload)
+}
+
+fn converse(inner: &GrpcFacadeInner, method: &GrpcMethod, payload: String) -> GrpcReply {
+    let client_id = inner.next_client_id.fetch_add(1, Ordering::Relaxed);
+    let (tx, rx) = mpsc::channel();
+    inner
+        .waiting_by_client_id
+        .lock()
+        .expect("gRPC facade is poisoned")
+        .insert(client_id, tx);
+    let sent = inner
+        .from_ui_message_sub
+        .lock()
+        .expect("gRPC facade is poisoned")
+        .try_send(NodeFromUiMessage {
+            client_id,
+            body: MessageBody {
+                opcode: method.opcode.to_string(),
+                path: Conversation(0),
+                payload: Ok(payload),
+            },
+        });
+    let received = match sent {
+        Ok(()) => rx.recv_timeout(RESPONSE_TIMEOUT).ok(),
+        Err(_) => None,
+    };
+    inner
+        .waiting_by_client_id
+        .lock()
+        .expect("gRPC facade is poisoned")
+        .remove(&client_id);
+    match received {
+        Some(body) => match body.payload {
+            Ok(payload) => match serde_json::from_str(&payload)
+                .map_err(|e| format!("{}", e))
+                .and_then(|json| protobuf_from_json(method.response, &json))
+            {
+                Ok(data) => GrpcReply {
+                    status: GRPC_OK,
+                    message: String::new(),
+                    error_code_opt: None,
+                    data_opt: Some(data),
+                },
+                Err(message) => GrpcReply::error(
+                    GRPC_INTERNAL,
+                    &format!(
+                        "Couldn't translate the {} response: {}",
+                        method.name, message
+                    ),
+                ),
+            },
+            Err((code, message)) => GrpcReply {
+                status: GRPC_UNKNOWN,
+                message,
+                error_code_opt: Some(code),
+                data_opt: None,
+            },
+        },
+        None => GrpcReply::error(
+            GRPC_DEADLINE_EXCEEDED,
+            &format!("The Node didn't answer the {} call", method.name),
+        ),
+    }
+}
+
+// grpc-message is percent-encoded, so that any message can travel in a header
+fn percent_encode(message: &str) -> String {
+    message
+        .bytes()
+        .map(|byte| match byte {
+            b' '..=b'~' if byte != b'%' => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::recorder::{make_recorder, RecordAwaiter, Recording};
+    use crate::ui_gateway::grpc_schema::{
+        FINANCIALS_METHOD, FINANCIALS_REQUEST, FINANCIALS_RESPONSE, NODE_METHODS,
+    };
+    use actix::{Actor, System};
+    use masq_lib::messages::{
+        FromMessageBody, ToMessageBody, UiFinancialsRequest, UiFinancialsResponse,
+        UiRouteFailureBroadcast, UNMARSHAL_ERROR,
+    };
+    use masq_lib::ui_gateway::MessageTarget::AllClients;
+    use masq_lib::utils::find_free_port;
+    use serde_json::json;
+    use std::sync::mpsc::Receiver;
+
+    fn start_subject(
+        methods: &'static [&'static GrpcMethod],
+        auth_opt: Option<UiAuthConfig>,
+    ) -> (GrpcFacade, Arc<Mutex<Recording>>, RecordAwaiter) {
+        let port = find_free_port();
+        let (ui_gateway, awaiter, recording_arc) = make_recorder();
+        let (tx, rx): (Sender<GrpcFacade>, Receiver<GrpcFacade>) = mpsc::channel();
+        thread::spawn(move || {
+            let system = System::new("grpc_facade");
+            let from_ui_message_sub = ui_gateway.start().recipient::<NodeFromUiMessage>();
+            tx.send(GrpcFacade::start(port, methods, auth_opt, from_ui_message_sub).unwrap())
+                .unwrap();
+            system.run();
+        });
+        (rx.recv().unwrap(), recording_arc, awaiter)
+    }
+
+    fn make_call(
+        path: &str,
+        authorization_opt: Option<&str>,
+        request: serde_json::Value,
+    ) -> GrpcCall {
+        GrpcCall {
+            path: path.to_string(),
+            authorization_opt: authorization_opt.map(|value| value.to_string()),
+            data: frame(&protobuf_from_json(&FINANCIALS_REQUEST, &request).unwrap()),
+        }
+    }
+
+    fn call(subject: &GrpcFacade, call: GrpcCall) -> thread::JoinHandle<GrpcReply> {
+        let inner = subject.inner.clone();
+        thread::spawn(move || answer(&inner, localhost(), call))
+    }
+
+    #[test]
+    fn calls_become_conversations_and_responses_come_back_as_protobuf() {
+        let (subject, recording_arc, awaiter) = start_subject(NODE_METHODS, None);
+
+        let client = call(
+            &subject,
+            make_call(
+                "/masq.ui.v1.MasqControl/Financials",
+                None,
+                json!({
+                    "payableMinimumAmount": 1,
+                    "payableMaximumAge": 2,
+                    "receivableMinimumAmount": 3,
+                    "receivableMaximumAge": 4,
+                }),
+            ),
+        );
+        awaiter.await_message_count(1);
+        let request = recording_arc
+            .lock()
+            .unwrap()
+            .get_record::<NodeFromUiMessage>(0)
+            .clone();
+        let broadcast = NodeToUiMessage {
+            target: AllClients,
+            body: UiRouteFailureBroadcast {
+                hostname: "booga.com".to_string(),
+            }
+            .tmb(0),
+        };
+        let response = UiFinancialsResponse {
+            payables: vec![],
+            total_payable: 1234,
+            receivables: vec![],
+            total_receivable: 4321,
+        };
+        let response_json = serde_json::to_value(&response).unwrap();
+        let unclaimed = subject.claim(broadcast.clone());
+        let claimed = subject.claim(NodeToUiMessage {
+            target: ClientId(GRPC_CLIENT_ID_BASE),
+            body: response.tmb(0),
+        });
+        let reply = client.join().unwrap();
+
+        assert_eq!(request.client_id, GRPC_CLIENT_ID_BASE);
+        assert_eq!(
+            UiFinancialsRequest::fmb(request.body).unwrap(),
+            (
+                UiFinancialsRequest {
+                    payable_minimum_amount: 1,
+                    payable_maximum_age: 2,
+                    receivable_minimum_amount: 3,
+                    receivable_maximum_age: 4,
+                },
+                0
+            )
+        );
+        assert_eq!(unclaimed, Some(broadcast));
+        assert_eq!(claimed, None);
+        assert_eq!(reply.status, GRPC_OK);
+        assert_eq!(
+            json_from_protobuf(&FINANCIALS_RESPONSE, &reply.data_opt.unwrap()).unwrap(),
+            response_json
+        );
+    }
+
+    #[test]
+    fn errors_from_the_node_come_back_with_their_codes() {
+        let (subject, _, awaiter) = start_subject(NODE_METHODS, None);
+
+        let client = call(
+            &subject,
+            make_call(&FINANCIALS_METHOD.path(), None, json!({})),
+        );
+        awaiter.await_message_count(1);
+        subject.claim(NodeToUiMessage {
+            target: ClientId(GRPC_CLIENT_ID_BASE),
+            body: MessageBody {
+                opcode: "financials".to_string(),
+                path: Conversation(0),
+                payload: Err((UNMARSHAL_ERROR, "missing field".to_string())),
+            },
+        });
+        let reply = client.join().unwrap();
+
+        assert_eq!(
+            reply,
+            GrpcReply {
+                status: GRPC_UNKNOWN,
+                message: "missing field".to_string(),
+                error_code_opt: Some(UNMARSHAL_ERROR),
+                data_opt: None,
+            }
+        );
+    }
+
+    #[test]
+    fn methods_served_elsewhere_are_unimplemented_here() {
+        let (subject, recording_arc, _) = start_subject(NODE_METHODS, None);
+
+        let reply = call(
+            &subject,
+            make_call("/masq.ui.v1.MasqControl/Setup", None, json!({})),
+        )
+        .join()
+        .unwrap();
+
+        assert_eq!(
+            reply,
+            GrpcReply::error(
+                GRPC_UNIMPLEMENTED,
+                "/masq.ui.v1.MasqControl/Setup isn't served here"
+            )
+        );
+        assert_eq!(recording_arc.lock().unwrap().len(), 0);
+    }
+
+    #[test]
+    fn passwords_are_required_when_the_node_has_them() {
+        let (subject, recording_arc, _) = start_subject(
+            NODE_METHODS,
+            Some(UiAuthConfig {
+                full_password: "booga".to_string(),
+                read_only_password_opt: Some("agoob".to_string()),
+            }),
+        );
+
+        let no_password = call(
+            &subject,
+            make_call("/masq.ui.v1.MasqControl/Shutdown", None, json!({})),
+        )
+        .join()
+        .unwrap();
+        let read_only = call(
+            &subject,
+            make_call(
+                "/masq.ui.v1.MasqControl/Shutdown",
+                Some("Bearer agoob"),
+                json!({}),
+            ),
+        )
+        .join()
+        .unwrap();
+
+        assert_eq!(no_password.status, GRPC_UNAUTHENTICATED);
+        assert_eq!(read_only.status, GRPC_PERMISSION_DENIED);
+        assert_eq!(recording_arc.lock().unwrap().len(), 0);
+    }
+
+    #[test]
+    fn messages_are_percent_encoded() {
+        assert_eq!(
+            percent_encode("100% sure: café"),
+            "100%25 sure: caf%C3%A9".to_string()
+        );
+    }
+}
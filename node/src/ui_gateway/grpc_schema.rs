@@ -0,0 +1,641 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use serde_json::{Map, Value};
+
+// Protobuf renderings of the UI messages the gRPC facade serves. Each field is known by the name
+// it has in the UI message's JSON, so one codec turns any of them into the JSON payload the
+// Node's actors expect and back; and proto/masq_ui.proto, which integrators generate their stubs
+// from, is generated from these tables too, so the two can't drift apart.
+pub const PROTO_PACKAGE: &str = "masq.ui.v1";
+pub const SERVICE_NAME: &str = "MasqControl";
+
+#[derive(Clone, Copy)]
+pub enum FieldType {
+    String,
+    Uint64,
+    Bool,
+    Message(&'static ProtoMessage),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Label {
+    Singular,
+    // proto3 'optional': absent is told apart from the default, and stands for JSON null
+    Optional,
+    Repeated,
+}
+
+pub struct ProtoField {
+    pub number: u32,
+    pub json_name: &'static str,
+    pub field_type: FieldType,
+    pub label: Label,
+}
+
+pub struct ProtoMessage {
+    pub name: &'static str,
+    pub fields: &'static [ProtoField],
+}
+
+pub struct GrpcMethod {
+    pub name: &'static str,
+    pub opcode: &'static str,
+    pub request: &'static ProtoMessage,
+    pub response: &'static ProtoMessage,
+}
+
+impl GrpcMethod {
+    pub fn path(&self) -> String {
+        format!("/{}.{}/{}", PROTO_PACKAGE, SERVICE_NAME, self.name)
+    }
+}
+
+const fn field(
+    number: u32,
+    json_name: &'static str,
+    field_type: FieldType,
+    label: Label,
+) -> ProtoField {
+    ProtoField {
+        number,
+        json_name,
+        field_type,
+        label,
+    }
+}
+
+pub static SETUP_REQUEST_VALUE: ProtoMessage = ProtoMessage {
+    name: "SetupRequestValue",
+    fields: &[
+        field(1, "name", FieldType::String, Label::Singular),
+        // Absent clears the parameter
+        field(2, "value", FieldType::String, Label::Optional),
+    ],
+};
+
+pub static SETUP_REQUEST: ProtoMessage = ProtoMessage {
+    name: "SetupRequest",
+    fields: &[
+        field(
+            1,
+            "values",
+            FieldType::Message(&SETUP_REQUEST_VALUE),
+            Label::Repeated,
+        ),
+        field(2, "instance", FieldType::String, Label::Optional),
+    ],
+};
+
+pub static SETUP_RESPONSE_VALUE: ProtoMessage = ProtoMessage {
+    name: "SetupResponseValue",
+    fields: &[
+        field(1, "name", FieldType::String, Label::Singular),
+        field(2, "value", FieldType::String, Label::Singular),
+        // Default, Configured, Set, Blank, or Required
+        field(3, "status", FieldType::String, Label::Singular),
+    ],
+};
+
+pub static SETUP_RESPONSE_ERROR: ProtoMessage = ProtoMessage {
+    name: "SetupResponseError",
+    fields: &[
+        field(1, "parameter", FieldType::String, Label::Singular),
+        // Invalid, Required, or Conflict
+        field(2, "code", FieldType::String, Label::Singular),
+        field(3, "message", FieldType::String, Label::Singular),
+    ],
+};
+
+pub static SETUP_RESPONSE: ProtoMessage = ProtoMessage {
+    name: "SetupResponse",
+    fields: &[
+        field(1, "running", FieldType::Bool, Label::Singular),
+        field(
+            2,
+            "values",
+            FieldType::Message(&SETUP_RESPONSE_VALUE),
+            Label::Repeated,
+        ),
+        field(
+            3,
+            "errors",
+            FieldType::Message(&SETUP_RESPONSE_ERROR),
+            Label::Repeated,
+        ),
+        field(4, "instance", FieldType::String, Label::Optional),
+    ],
+};
+
+pub static STATUS_REQUEST: ProtoMessage = ProtoMessage {
+    name: "StatusRequest",
+    fields: &[],
+};
+
+pub static STATUS_RESPONSE: ProtoMessage = ProtoMessage {
+    name: "StatusResponse",
+    fields: &[field(
+        1,
+        "nodeDescriptor",
+        FieldType::String,
+        Label::Singular,
+    )],
+};
+
+pub static FINANCIALS_REQUEST: ProtoMessage = ProtoMessage {
+    name: "FinancialsRequest",
+    fields: &[
+        field(
+            1,
+            "payableMinimumAmount",
+            FieldType::Uint64,
+            Label::Singular,
+        ),
+        field(2, "payableMaximumAge", FieldType::Uint64, Label::Singular),
+        field(
+            3,
+            "receivableMinimumAmount",
+            FieldType::Uint64,
+            Label::Singular,
+        ),
+        field(
+            4,
+            "receivableMaximumAge",
+            FieldType::Uint64,
+            Label::Singular,
+        ),
+    ],
+};
+
+pub static PAYABLE_ACCOUNT: ProtoMessage = ProtoMessage {
+    name: "PayableAccount",
+    fields: &[
+        field(1, "wallet", FieldType::String, Label::Singular),
+        field(2, "age", FieldType::Uint64, Label::Singular),
+        field(3, "amount", FieldType::Uint64, Label::Singular),
+        field(4, "pendingTransaction", FieldType::String, Label::Optional),
+    ],
+};
+
+pub static RECEIVABLE_ACCOUNT: ProtoMessage = ProtoMessage {
+    name: "ReceivableAccount",
+    fields: &[
+        field(1, "wallet", FieldType::String, Label::Singular),
+        field(2, "age", FieldType::Uint64, Label::Singular),
+        field(3, "amount", FieldType::Uint64, Label::Singular),
+    ],
+};
+
+pub static FINANCIALS_RESPONSE: ProtoMessage = ProtoMessage {
+    name: "FinancialsResponse",
+    fields: &[
+        field(
+            1,
+            "payables",
+            FieldType::Message(&PAYABLE_ACCOUNT),
+            Label::Repeated,
+        ),
+        field(2, "totalPayable", FieldType::Uint64, Label::Singular),
+        field(
+            3,
+            "receivables",
+            FieldType::Message(&RECEIVABLE_ACCOUNT),
+            Label::Repeated,
+        ),
+        field(4, "totalReceivable", FieldType::Uint64, Label::Singular),
+    ],
+};
+
+pub static SHUTDOWN_REQUEST: ProtoMessage = ProtoMessage {
+    name: "ShutdownRequest",
+    fields: &[],
+};
+
+pub static SHUTDOWN_RESPONSE: ProtoMessage = ProtoMessage {
+    name: "ShutdownResponse",
+    fields: &[],
+};
+
+pub static SETUP_METHOD: GrpcMethod = GrpcMethod {
+    name: "Setup",
+    opcode: "setup",
+    request: &SETUP_REQUEST,
+    response: &SETUP_RESPONSE,
+};
+
+pub static STATUS_METHOD: GrpcMethod = GrpcMethod {
+    name: "Status",
+    opcode: "descriptor",
+    request: &STATUS_REQUEST,
+    response: &STATUS_RESPONSE,
+};
+
+pub static FINANCIALS_METHOD: GrpcMethod = GrpcMethod {
+    name: "Financials",
+    opcode: "financials",
+    request: &FINANCIALS_REQUEST,
+    response: &FINANCIALS_RESPONSE,
+};
+
+pub static SHUTDOWN_METHOD: GrpcMethod = GrpcMethod {
+    name: "Shutdown",
+    opcode: "shutdown",
+    request: &SHUTDOWN_REQUEST,
+    response: &SHUTDOWN_RESPONSE,
+};
+
+// Setup is the Daemon's business; everything else is the Node's
+pub static DAEMON_METHODS: &[&GrpcMethod] = &[&SETUP_METHOD];
+pub static NODE_METHODS: &[&GrpcMethod] = &[&STATUS_METHOD, &FINANCIALS_METHOD, &SHUTDOWN_METHOD];
+
+pub fn json_from_protobuf(message: &ProtoMessage, data: &[u8]) -> Result<Value, String> {
+    let mut object = Map::new();
+    message.fields.iter().for_each(|field| {
+        let absent = match (field.label, field.field_type) {
+            (Label::Repeated, _) => Value::Array(vec![]),
+            (Label::Optional, _) => Value::Null,
+            (Label::Singular, FieldType::String) => Value::from(""),
+            (Label::Singular, FieldType::Uint64) => Value::from(0),
+            (Label::Singular, FieldType::Bool) => Value::from(false),
+            (Label::Singular, FieldType::Message(inner)) => {
+                json_from_protobuf(inner, &[]).expect("Empty message won't decode")
+            }
+        };
+        object.insert(field.json_name.to_string(), absent);
+    });
+    let mut offset = 0;
+    while offset < data.len() {
+        let key = read_varint(data, &mut offset)?;
+        let (number, wire_type) = ((key >> 3) as u32, (key & 7) as u8);
+        let field_opt = message.fields.iter().find(|field| field.number == number);
+        let value = match (field_opt, wire_type) {
+            (Some(field), 0) => match field.field_type {
+                FieldType::Uint64 => Value::from(read_varint(data, &mut offset)?),
+                FieldType::Bool => Value::from(read_varint(data, &mut offset)? != 0),
+                _ => return Err(wrong_wire_type(message, field, wire_type)),
+            },
+            (Some(field), 2) => {
+                let bytes = read_length_delimited(data, &mut offset)?;
+                match field.field_type {
+                    FieldType::String => {
+                        Value::from(String::from_utf8(bytes.to_vec()).map_err(|_| {
+                            format!("{}.{} isn't UTF-8", message.name, field.json_name)
+                        })?)
+                    }
+                    FieldType::Message(inner) => json_from_protobuf(inner, bytes)?,
+                    _ => return Err(wrong_wire_type(message, field, wire_type)),
+                }
+            }
+            (Some(field), _) => return Err(wrong_wire_type(message, field, wire_type)),
+            // Fields from a newer version of the .proto are skipped, as protobuf intends
+            (None, _) => {
+                skip_field(data, &mut offset, wire_type)?;
+                continue;
+            }
+        };
+        let field = field_opt.expect("Field disappeared");
+        match field.label {
+            Label::Repeated => match object.get_mut(field.json_name) {
+                Some(Value::Array(values)) => values.push(value),
+                _ => panic!("Repeated field {} isn't an array", field.json_name),
+            },
+            _ => {
+                object.insert(field.json_name.to_string(), value);
+            }
+        }
+    }
+    Ok(Value::Object(object))
+}
+
+pub fn protobuf_from_json(message: &ProtoMessage, value: &Value) -> Result<Vec<u8>, String> {
+    let object = match value {
+        Value::Object(object) => object,
+        _ => return Err(format!("{} isn't a JSON object", message.name)),
+    };
+    let mut data = vec![];
+    for field in message.fields {
+        let field_value = object.get(field.json_name).unwrap_or(&Value::Null);
+        match (field.label, field_value) {
+            (_, Value::Null) => (),
+            (Label::Repeated, Value::Array(values)) => {
+                for value in values {
+                    write_field(&mut data, message, field, value, true)?
+                }
+            }
+            (Label::Repeated, _) => {
+                return Err(format!(
+                    "{}.{} isn't an array",
+                    message.name, field.json_name
+                ))
+            }
+            (Label::Optional, value) => write_field(&mut data, message, field, value, true)?,
+            (Label::Singular, value) => write_field(&mut data, message, field, value, false)?,
+        }
+    }
+    Ok(data)
+}
+
+// A gRPC message on the wire: a compression flag, a big-endian length, and the protobuf
+pub fn frame(message: &[u8]) -> Vec<u8> {
+    let mut framed = vec![0u8];
+    framed.extend_from_slice(&(message.len() as u32).to_be_bytes());
+    framed.extend_from_slice(message);
+    framed
+}
+
+pub fn unframe(data: &[u8]) -> Result<&[u8], String> {
+    if data.len() < 5 {
+        return Err("Request is too short to be a gRPC message".to_string());
+    }
+    if data[0] != 0 {
+        return Err("Compressed requests aren't supported".to_string());
+    }
+    let length = u32::from_be_bytes([data[1], data[2], data[3], data[4]]) as usize;
+    if data.len() != 5 + length {
+        return Err(format!(
+            "gRPC message says it's {} bytes long, but it's {}",
+            length,
+            data.len() - 5
+        ));
+    }
+    Ok(&data[5..])
+}
+
+fn write_field(
+    data: &mut Vec<u8>,
+    message: &ProtoMessage,
+    field: &ProtoField,
+    value: &Value,
+    keep_default: bool,
+) -> Result<(), String> {
+    let mismatch = || format!("{}.{} can't be {}", message.name, field.json_name, value);
+    match field.field_type {
+        FieldType::String => {
+            let string = value.as_str().ok_or_else(mismatch)?;
+            if keep_default || !string.is_empty() {
+                write_length_delimited(data, field.number, string.as_bytes());
+            }
+        }
+        FieldType::Uint64 => {
+            let number = value.as_u64().ok_or_else(mismatch)?;
+            if keep_default || number != 0 {
+                write_varint(data, u64::from(field.number << 3));
+                write_varint(data, number);
+            }
+        }
+        FieldType::Bool => {
+            let flag = value.as_bool().ok_or_else(mismatch)?;
+            if keep_default || flag {
+                write_varint(data, u64::from(field.number << 3));
+                write_varint(data, u64::from(flag));
+            }
+        }
+        FieldType::Message(inner) => {
+            let encoded = protobuf_from_json(inner, value)?;
+            write_length_delimited(data, field.number, &encoded);
+        }
+    }
+    Ok(())
+}
+
+fn write_length_delimited(data: &mut Vec<u8>, number: u32, bytes: &[u8]) {
+    write_varint(data, u64::from(number << 3 | 2));
+    write_varint(data, bytes.len() as u64);
+    data.extend_from_slice(bytes);
+}
+
+fn write_varint(data: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        data.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    data.push(value as u8);
+}
+
+fn read_varint(data: &[u8], offset: &mut usize) -> Result<u64, String> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *data
+            .get(*offset)
+            .ok_or_else(|| "Protobuf ends in the middle of a number".to_string())?;
+        *offset += 1;
+        value |= u64::from(byte & 0x7F) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err("Protobuf number is too long".to_string())
+}
+
+fn read_length_delimited<'a>(data: &'a [u8], offset: &mut usize) -> Result<&'a [u8], String> {
+    let length = read_varint(data, offset)? as usize;
+    let end = offset
+        .checked_add(length)
+        .filter(|end| *end <= data.len())
+        .ok_or_else(|| "Protobuf ends in the middle of a field".to_string())?;
+    let bytes = &data[*offset..end];
+    *offset = end;
+    Ok(bytes)
+}
+
+fn skip_field(data: &[u8], offset: &mut usize, wire_type: u8) -> Result<(), String> {
+    let width = match wire_type {
+        0 => return read_varint(data, offset).map(|_| ()),
+        1 => 8,
+        2 => return read_length_delimited(data, offset).map(|_| ()),
+        5 => 4,
+        _ => return Err(format!("Unknown protobuf wire type {}", wire_type)),
+    };
+    if *offset + width > data.len() {
+        return Err("Protobuf ends in the middle of a field".to_string());
+    }
+    *offset += width;
+    Ok(())
+}
+
+fn wrong_wire_type(message: &ProtoMessage, field: &ProtoField, wire_type: u8) -> String {
+    format!(
+        "{}.{} can't arrive as protobuf wire type {}",
+        message.name, field.json_name, wire_type
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use heck::SnakeCase;
+    use masq_lib::messages::{
+        UiFinancialsResponse, UiPayableAccount, UiReceivableAccount, UiSetupRequest,
+    };
+    use serde_json::json;
+
+    static ALL_METHODS: &[&GrpcMethod] = &[
+        &SETUP_METHOD,
+        &STATUS_METHOD,
+        &FINANCIALS_METHOD,
+        &SHUTDOWN_METHOD,
+    ];
+
+    fn proto_definition(methods: &[&GrpcMethod]) -> String {
+        let mut messages: Vec<&'static ProtoMessage> = vec![];
+        methods.iter().for_each(|method| {
+            collect_messages(method.request, &mut messages);
+            collect_messages(method.response, &mut messages);
+        });
+        let mut proto = format!(
+            "// Generated from the MASQ UI message types; don't edit by hand.\n\
+             syntax = \"proto3\";\n\npackage {};\n\nservice {} {{\n",
+            PROTO_PACKAGE, SERVICE_NAME
+        );
+        methods.iter().for_each(|method| {
+            proto.push_str(&format!(
+                "    rpc {}({}) returns ({});\n",
+                method.name, method.request.name, method.response.name
+            ))
+        });
+        proto.push_str("}\n");
+        messages.iter().for_each(|message| {
+            proto.push_str(&format!("\nmessage {} {{\n", message.name));
+            message.fields.iter().for_each(|field| {
+                let label = match field.label {
+                    Label::Singular => "",
+                    Label::Optional => "optional ",
+                    Label::Repeated => "repeated ",
+                };
+                let type_name = match field.field_type {
+                    FieldType::String => "string",
+                    FieldType::Uint64 => "uint64",
+                    FieldType::Bool => "bool",
+                    FieldType::Message(message) => message.name,
+                };
+                proto.push_str(&format!(
+                    "    {}{} {} = {};\n",
+                    label,
+                    type_name,
+                    field.json_name.to_snake_case(),
+                    field.number
+                ))
+            });
+            proto.push_str("}\n");
+        });
+        proto
+    }
+
+    fn collect_messages(message: &'static ProtoMessage, messages: &mut Vec<&'static ProtoMessage>) {
+        if messages.iter().any(|known| known.name == message.name) {
+            return;
+        }
+        messages.push(message);
+        message.fields.iter().for_each(|field| {
+            if let FieldType::Message(inner) = field.field_type {
+                collect_messages(inner, messages)
+            }
+        });
+    }
+
+    #[test]
+    fn the_checked_in_proto_file_is_the_one_the_tables_make() {
+        let checked_in = include_str!("../../proto/masq_ui.proto");
+
+        assert_eq!(
+            checked_in,
+            proto_definition(ALL_METHODS),
+            "proto/masq_ui.proto is stale; regenerate it from proto_definition(ALL_METHODS)"
+        );
+    }
+
+    #[test]
+    fn ui_json_survives_a_round_trip_through_protobuf() {
+        let response = UiFinancialsResponse {
+            payables: vec![UiPayableAccount {
+                wallet: "0x0123".to_string(),
+                age: 300,
+                amount: 1_000_000_000_000,
+                pending_transaction: None,
+            }],
+            total_payable: 1_000_000_000_000,
+            receivables: vec![UiReceivableAccount {
+                wallet: "0x4567".to_string(),
+                age: 0,
+                amount: 5,
+            }],
+            total_receivable: 5,
+        };
+        let json = serde_json::to_value(&response).unwrap();
+
+        let protobuf = protobuf_from_json(&FINANCIALS_RESPONSE, &json).unwrap();
+        let result = json_from_protobuf(&FINANCIALS_RESPONSE, &protobuf).unwrap();
+
+        assert_eq!(result, json);
+    }
+
+    #[test]
+    fn optional_fields_tell_absent_from_empty() {
+        let request = UiSetupRequest::new(vec![("neighbors", None), ("ip", Some(""))]);
+        let json = serde_json::to_value(&request).unwrap();
+
+        let protobuf = protobuf_from_json(&SETUP_REQUEST, &json).unwrap();
+        let result = json_from_protobuf(&SETUP_REQUEST, &protobuf).unwrap();
+
+        assert_eq!(
+            serde_json::from_value::<UiSetupRequest>(result).unwrap(),
+            request
+        );
+    }
+
+    #[test]
+    fn unknown_fields_are_skipped_and_missing_ones_take_their_defaults() {
+        let mut data = vec![];
+        write_varint(&mut data, 9 << 3);
+        write_varint(&mut data, 1234);
+        write_length_delimited(&mut data, 10, b"from the future");
+        write_varint(&mut data, 2 << 3);
+        write_varint(&mut data, 7);
+
+        let result = json_from_protobuf(&FINANCIALS_REQUEST, &data).unwrap();
+
+        assert_eq!(
+            result,
+            json!({
+                "payableMinimumAmount": 0,
+                "payableMaximumAge": 7,
+                "receivableMinimumAmount": 0,
+                "receivableMaximumAge": 0,
+            })
+        );
+    }
+
+    #[test]
+    fn malformed_protobuf_and_mismatched_json_are_reported() {
+        let truncated = json_from_protobuf(&STATUS_RESPONSE, &[0x0A, 0x05, b'a']);
+        let wrong_wire_type = json_from_protobuf(&STATUS_RESPONSE, &[0x08, 0x01]);
+        let mismatched = protobuf_from_json(&STATUS_RESPONSE, &json!({"nodeDescriptor": 5}));
+
+        assert_eq!(
+            truncated,
+            Err("Protobuf ends in the middle of a field".to_string())
+        );
+        assert_eq!(
+            wrong_wire_type,
+            Err("StatusResponse.nodeDescriptor can't arrive as protobuf wire type 0".to_string())
+        );
+        assert_eq!(
+            mismatched,
+            Err("StatusResponse.nodeDescriptor can't be 5".to_string())
+        );
+    }
+
+    #[test]
+    fn grpc_frames_carry_their_length() {
+        let framed = frame(b"booga");
+
+        assert_eq!(framed, vec![0, 0, 0, 0, 5, b'b', b'o', b'o', b'g', b'a']);
+        assert_eq!(unframe(&framed), Ok(&b"booga"[..]));
+        assert_eq!(
+            unframe(&[1, 0, 0, 0, 0]),
+            Err("Compressed requests aren't supported".to_string())
+        );
+        assert_eq!(
+            unframe(&[0, 0, 0, 0, 9, 1]),
+            Err("gRPC message says it's 9 bytes long, but it's 1".to_string())
+        );
+    }
+}
@@ -0,0 +1,504 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use crate::database::db_initializer::DATABASE_FILE;
+use crate::sub_lib::health::{neighborhood_readiness, NeighborhoodReadiness};
+use crate::sub_lib::logger::Logger;
+use actix::Recipient;
+use futures::Future;
+use masq_lib::messages::{UiHealthCheck, UiHealthResponse};
+use masq_lib::ui_gateway::MessagePath::FireAndForget;
+use masq_lib::ui_gateway::{MessageBody, NodeFromUiMessage};
+use rusqlite::{Connection, OpenFlags, NO_PARAMS};
+use std::io;
+use std::io::{Read, Write};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Nobody handles this opcode; an actor that gets around to ignoring it is responsive
+const PROBE_OPCODE: &str = "healthProbe";
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_REQUEST_HEAD_SIZE: usize = 8192;
+
+#[derive(Clone)]
+pub struct HealthChecker {
+    actors: Vec<(&'static str, Recipient<NodeFromUiMessage>)>,
+    data_directory: PathBuf,
+}
+
+impl HealthChecker {
+    pub fn new(
+        actors: Vec<(&'static str, Recipient<NodeFromUiMessage>)>,
+        data_directory: PathBuf,
+    ) -> HealthChecker {
+        HealthChecker {
+            actors,
+            data_directory,
+        }
+    }
+
+    // Blocks for as long as the slowest actor takes to answer, up to PROBE_TIMEOUT
+    pub fn check(&self) -> UiHealthResponse {
+        combine(
+            vec![self.check_actors(), self.check_database()],
+            readiness_checks(neighborhood_readiness()),
+        )
+    }
+
+    fn check_actors(&self) -> UiHealthCheck {
+        let (tx, rx) = mpsc::channel();
+        self.actors.iter().for_each(|(name, recipient)| {
+            let (name, recipient, tx) = (*name, recipient.clone(), tx.clone());
+            // A stuck actor keeps its thread until it comes unstuck or dies
+            thread::spawn(move || {
+                let answered = recipient.send(probe()).wait().is_ok();
+                let _ = tx.send((name, answered));
+            });
+        });
+        let deadline = Instant::now() + PROBE_TIMEOUT;
+        let mut answered = vec![];
+        while answered.len() < self.actors.len() {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            match rx.recv_timeout(timeout) {
+                Ok((name, true)) => answered.push(name),
+                Ok((_, false)) => (),
+                Err(_) => break,
+            }
+        }
+        let silent = self
+            .actors
+            .iter()
+            .map(|(name, _)| *name)
+            .filter(|name| !answered.contains(name))
+            .collect::<Vec<&str>>();
+        UiHealthCheck {
+            name: "actors".to_string(),
+            passed: silent.is_empty(),
+            detail: if silent.is_empty() {
+                format!("{} actors answered", self.actors.len())
+            } else {
+                format!(
+                    "No answer within {} seconds from {}",
+                    PROBE_TIMEOUT.as_secs(),
+                    silent.join(", ")
+                )
+            },
+        }
+    }
+
+    fn check_database(&self) -> UiHealthCheck {
+        let result = Connection::open_with_flags(
+            self.data_directory.join(DATABASE_FILE),
+            OpenFlags::SQLITE_OPEN_READ_ONLY,
+        )
+        .and_then(|conn| {
+            conn.query_row(
+                "select value from config where name = 'schema_version'",
+                NO_PARAMS,
+                |row| row.get::<usize, String>(0),
+            )
+        });
+        UiHealthCheck {
+            name: "database".to_string(),
+            passed: result.is_ok(),
+            detail: match result {
+                Ok(version) => format!("Schema version {}", version),
+                Err(e) => format!("Can't read the database: {}", e),
+            },
+        }
+    }
+}
+
+// Serves the checks over plain HTTP for orchestrators: /health for liveness and /ready for
+// readiness, each 200 if it passes and 503 if it doesn't. It says nothing but how the checks
+// went, so it listens on every interface; the orchestrator's probes rarely come from localhost.
+pub fn start_health_server(port: u16, checker: HealthChecker) -> io::Result<SocketAddr> {
+    let listener = TcpListener::bind(SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), port))?;
+    let local_addr = listener.local_addr()?;
+    let logger = Logger::new("HealthServer");
+    info!(
+        logger,
+        "Serving health checks on port {}",
+        local_addr.port()
+    );
+    thread::spawn(move || {
+        listener
+            .incoming()
+            .for_each(|stream_result| match stream_result {
+                Ok(stream) => {
+                    let (checker, logger) = (checker.clone(), logger.clone());
+                    thread::spawn(move || {
+                        if let Err(e) = handle_connection(&checker, stream) {
+                            debug!(logger, "Couldn't answer health request: {}", e);
+                        }
+                    });
+                }
+                Err(e) => warning!(logger, "Couldn't accept health connection: {}", e),
+            })
+    });
+    Ok(local_addr)
+}
+
+// Under systemd with WatchdogSec set, tells it the Node is alive every half watchdog interval,
+// as long as the liveness checks pass, and that it's ready the first time they do.
+#[cfg(unix)]
+pub fn start_systemd_watchdog(checker: HealthChecker) {
+    use std::os::unix::net::UnixDatagram;
+    let logger = Logger::new("HealthChecker");
+    let socket_path = match std::env::var("NOTIFY_SOCKET") {
+        Ok(path) => path,
+        Err(_) => return,
+    };
+    let interval = match watchdog_interval(
+        std::env::var("WATCHDOG_USEC").ok(),
+        std::env::var("WATCHDOG_PID").ok(),
+        std::process::id(),
+    ) {
+        Some(interval) => interval,
+        None => return,
+    };
+    if socket_path.starts_with('@') {
+        warning!(
+            logger,
+            "Can't feed the systemd watchdog through abstract socket {}",
+            socket_path
+        );
+        return;
+    }
+    thread::spawn(move || {
+        let mut notified_ready = false;
+        loop {
+            if checker.check().healthy {
+                let state = if notified_ready {
+                    "WATCHDOG=1"
+                } else {
+                    "READY=1\nWATCHDOG=1"
+                };
+                match UnixDatagram::unbound()
+                    .and_then(|socket| socket.send_to(state.as_bytes(), &socket_path))
+                {
+                    Ok(_) => notified_ready = true,
+                    Err(e) => warning!(logger, "Couldn't notify systemd: {}", e),
+                }
+            }
+            thread::sleep(interval);
+        }
+    });
+}
+
+// Half the interval systemd gave us, if the watchdog is meant for this process
+#[cfg(unix)]
+fn watchdog_interval(
+    usec_opt: Option<String>,
+    pid_opt: Option<String>,
+    own_pid: u32,
+) -> Option<Duration> {
+    if let Some(pid) = pid_opt {
+        if pid.parse::<u32>().ok() != Some(own_pid) {
+            return None;
+        }
+    }
+    match usec_opt.and_then(|usec| usec.parse::<u64>().ok()) {
+        Some(usec) if usec > 0 => Some(Duration::from_micros(usec / 2)),
+        _ => None,
+    }
+}
+
+fn probe() -> NodeFromUiMessage {
+    NodeFromUiMessage {
+        client_id: 0,
+        body: MessageBody {
+            opcode: PROBE_OPCODE.to_string(),
+            path: FireAndForget,
+            payload: Ok("{}".to_string()),
+        },
+    }
+}
+
+fn readiness_checks(readiness: NeighborhoodReadiness) -> Vec<UiHealthCheck> {
+    vec![
+        UiHealthCheck {
+            name: "neighborhood".to_string(),
+            passed: readiness.connected,
+            detail: if readiness.connected {
+                "Connected to the MASQ Network".to_string()
+            } else {
+                "No full neighbors yet".to_string()
+            },
+        },
+        UiHealthCheck {
+            name: "routes".to_string(),
+            passed: readiness.routes_available,
+            detail: if readiness.routes_available {
+                "Round-trip routes are available".to_string()
+            } else {
+                "Not enough of the network is known to make a route".to_string()
+            },
+        },
+    ]
+}
+
+fn combine(liveness: Vec<UiHealthCheck>, readiness: Vec<UiHealthCheck>) -> UiHealthResponse {
+    let healthy = liveness.iter().all(|check| check.passed);
+    let ready = healthy && readiness.iter().all(|check| check.passed);
+    UiHealthResponse {
+        healthy,
+        ready,
+        checks: liveness.into_iter().chain(readiness).collect(),
+    }
+}
+
+fn handle_connection(checker: &HealthChecker, mut stream: TcpStream) -> io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut head = vec![];
+    let mut buf = [0u8; 1024];
+    while !head.windows(4).any(|window| window == b"\r\n\r\n")
+        && head.len() <= MAX_REQUEST_HEAD_SIZE
+    {
+        let len = stream.read(&mut buf)?;
+        if len == 0 {
+            break;
+        }
+        head.extend_from_slice(&buf[..len]);
+    }
+    let head = String::from_utf8_lossy(&head).to_string();
+    let request_line = head.lines().next().unwrap_or("");
+    stream.write_all(&response_to(request_line, || checker.check()))?;
+    stream.flush()
+}
+
+fn response_to<F>(request_line: &str, check: F) -> Vec<u8>
+where
+    F: FnOnce() -> UiHealthResponse,
+{
+    let mut words = request_line.split_whitespace();
+    if words.next() != Some("GET") {
+        return http_response(
+            "405 Method Not Allowed",
+            "{\"error\":\"Only GET is supported\"}",
+        );
+    }
+    let target = words.next().unwrap_or("");
+    let path = target.split('?').next().unwrap_or("").trim_end_matches('/');
+    let response = match path {
+        "/health" | "/ready" => check(),
+        _ => return http_response("404 Not Found", "{\"error\":\"Try /health or /ready\"}"),
+    };
+    let passed = if path == "/health" {
+        response.healthy
+    } else {
+        response.ready
+    };
+    let body = serde_json::to_string(&response).expect("Serialization failed");
+    http_response(
+        if passed {
+            "200 OK"
+        } else {
+            "503 Service Unavailable"
+        },
+        &body,
+    )
+}
+
+fn http_response(status: &str, body: &str) -> Vec<u8> {
+    format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )
+    .into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::db_initializer::CURRENT_SCHEMA_VERSION;
+    use crate::database::db_initializer::{DbInitializer, DbInitializerReal};
+    use crate::test_utils::recorder::make_recorder;
+    use actix::{Actor, System};
+    use masq_lib::test_utils::utils::{ensure_node_home_directory_exists, DEFAULT_CHAIN_ID};
+    use masq_lib::utils::find_free_port;
+
+    fn check(name: &str, passed: bool) -> UiHealthCheck {
+        UiHealthCheck {
+            name: name.to_string(),
+            passed,
+            detail: "".to_string(),
+        }
+    }
+
+    #[test]
+    fn responsive_actors_and_a_readable_database_are_healthy() {
+        let data_directory = ensure_node_home_directory_exists(
+            "health",
+            "responsive_actors_and_a_readable_database_are_healthy",
+        );
+        DbInitializerReal::new()
+            .initialize(&data_directory, DEFAULT_CHAIN_ID, true)
+            .unwrap();
+        let (accountant, _, accountant_recording_arc) = make_recorder();
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let system = System::new("responsive_actors_and_a_readable_database_are_healthy");
+            tx.send(accountant.start().recipient::<NodeFromUiMessage>())
+                .unwrap();
+            system.run();
+        });
+        let subject = HealthChecker::new(vec![("Accountant", rx.recv().unwrap())], data_directory);
+
+        let actors = subject.check_actors();
+        let database = subject.check_database();
+
+        assert_eq!(
+            actors,
+            UiHealthCheck {
+                name: "actors".to_string(),
+                passed: true,
+                detail: "1 actors answered".to_string(),
+            }
+        );
+        assert_eq!(
+            database,
+            UiHealthCheck {
+                name: "database".to_string(),
+                passed: true,
+                detail: format!("Schema version {}", CURRENT_SCHEMA_VERSION),
+            }
+        );
+        let accountant_recording = accountant_recording_arc.lock().unwrap();
+        assert_eq!(
+            accountant_recording.get_record::<NodeFromUiMessage>(0),
+            &probe()
+        );
+    }
+
+    #[test]
+    fn a_missing_database_is_unhealthy() {
+        let data_directory =
+            ensure_node_home_directory_exists("health", "a_missing_database_is_unhealthy");
+        let subject = HealthChecker::new(vec![], data_directory);
+
+        let result = subject.check_database();
+
+        assert_eq!(result.passed, false);
+        assert_eq!(
+            result.detail.starts_with("Can't read the database: "),
+            true,
+            "{}",
+            result.detail
+        );
+    }
+
+    #[test]
+    fn readiness_needs_health_as_well() {
+        let unhealthy = combine(
+            vec![check("actors", true), check("database", false)],
+            vec![check("neighborhood", true), check("routes", true)],
+        );
+        let unready = combine(
+            vec![check("actors", true), check("database", true)],
+            vec![check("neighborhood", true), check("routes", false)],
+        );
+        let ready = combine(
+            vec![check("actors", true)],
+            readiness_checks(NeighborhoodReadiness {
+                connected: true,
+                routes_available: true,
+            }),
+        );
+
+        assert_eq!((unhealthy.healthy, unhealthy.ready), (false, false));
+        assert_eq!((unready.healthy, unready.ready), (true, false));
+        assert_eq!((ready.healthy, ready.ready), (true, true));
+        assert_eq!(unhealthy.checks.len(), 4);
+    }
+
+    #[test]
+    fn http_status_follows_the_check_asked_about() {
+        let unready = || combine(vec![check("actors", true)], vec![check("routes", false)]);
+
+        let health = String::from_utf8(response_to("GET /health HTTP/1.1", unready)).unwrap();
+        let ready = String::from_utf8(response_to("GET /ready/ HTTP/1.1", unready)).unwrap();
+        let elsewhere = String::from_utf8(response_to("GET /status HTTP/1.1", unready)).unwrap();
+        let posted = String::from_utf8(response_to("POST /health HTTP/1.1", unready)).unwrap();
+
+        assert_eq!(
+            health.starts_with("HTTP/1.1 200 OK\r\n"),
+            true,
+            "{}",
+            health
+        );
+        assert_eq!(
+            health.ends_with(
+                "\r\n\r\n{\"healthy\":true,\"ready\":false,\"checks\":[{\"name\":\"actors\",\"passed\":true,\"detail\":\"\"},{\"name\":\"routes\",\"passed\":false,\"detail\":\"\"}]}"
+            ),
+            true,
+            "{}",
+            health
+        );
+        assert_eq!(
+            ready.starts_with("HTTP/1.1 503 Service Unavailable\r\n"),
+            true,
+            "{}",
+            ready
+        );
+        assert_eq!(elsewhere.starts_with("HTTP/1.1 404 Not Found\r\n"), true);
+        assert_eq!(
+            posted.starts_with("HTTP/1.1 405 Method Not Allowed\r\n"),
+            true
+        );
+    }
+
+    #[test]
+    fn health_server_answers_over_http() {
+        let data_directory =
+            ensure_node_home_directory_exists("health", "health_server_answers_over_http");
+        let port = find_free_port();
+        let server_addr =
+            start_health_server(port, HealthChecker::new(vec![], data_directory)).unwrap();
+        let mut stream =
+            TcpStream::connect(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port)).unwrap();
+
+        stream
+            .write_all(b"GET /health HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+
+        assert_eq!(server_addr.port(), port);
+        assert_eq!(
+            response.starts_with("HTTP/1.1 503 Service Unavailable\r\n"),
+            true,
+            "{}",
+            response
+        );
+        assert_eq!(
+            response.contains("\"name\":\"database\",\"passed\":false"),
+            true,
+            "{}",
+            response
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn watchdog_interval_is_half_what_systemd_asks_for_this_process() {
+        assert_eq!(
+            watchdog_interval(Some("30000000".to_string()), None, 1234),
+            Some(Duration::from_secs(15))
+        );
+        assert_eq!(
+            watchdog_interval(Some("30000000".to_string()), Some("1234".to_string()), 1234),
+            Some(Duration::from_secs(15))
+        );
+        assert_eq!(
+            watchdog_interval(Some("30000000".to_string()), Some("4321".to_string()), 1234),
+            None
+        );
+        assert_eq!(watchdog_interval(None, None, 1234), None);
+        assert_eq!(watchdog_interval(Some("0".to_string()), None, 1234), None);
+    }
+}
@@ -0,0 +1,320 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use masq_lib::messages::{
+    UiDnsRevertRequest, UiDnsSubvertRequest, UiLogLevelRequest, UiRotateLogRequest,
+    UiSetApplicationRulesRequest, UiSetSplitTunnelRulesRequest, UiSetupRequest, UiShutdownRequest,
+    UiStartOrder, DUPLICATE_REQUEST_ERROR,
+};
+use masq_lib::ui_gateway::MessagePath::Conversation;
+use masq_lib::ui_gateway::{MessageBody, MessageTarget, NodeFromUiMessage, NodeToUiMessage};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+pub const IDEMPOTENCY_KEY_FIELD: &str = "idempotencyKey";
+// Long enough for a UI to notice a dropped connection, reconnect, and retry
+pub const IDEMPOTENCY_KEY_LIFETIME: Duration = Duration::from_secs(300);
+const MAX_IDEMPOTENCY_KEYS: usize = 1000;
+
+// Requests that change something, so that applying one twice isn't the same as applying it once
+pub fn takes_idempotency_key(opcode: &str) -> bool {
+    [
+        UiDnsRevertRequest::type_opcode(),
+        UiDnsSubvertRequest::type_opcode(),
+        UiLogLevelRequest::type_opcode(),
+        UiRotateLogRequest::type_opcode(),
+        UiSetApplicationRulesRequest::type_opcode(),
+        UiSetSplitTunnelRulesRequest::type_opcode(),
+        UiSetupRequest::type_opcode(),
+        UiShutdownRequest::type_opcode(),
+        UiStartOrder::type_opcode(),
+    ]
+    .contains(&opcode)
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Admission {
+    Fresh,
+    // Said again: the response to the first time, or an error if there isn't one yet
+    Answered(NodeToUiMessage),
+}
+
+struct Request {
+    payload: Value,
+    response_opt: Option<Result<String, (u64, String)>>,
+    expires: Instant,
+}
+
+// Remembers the state-changing requests that carried an idempotency key, and what the Node or
+// Daemon said about them, so that a UI retrying one after a reconnect gets the same answer
+// instead of having it applied twice.
+#[derive(Default)]
+pub struct IdempotencyCache {
+    requests: HashMap<(String, String), Request>,
+    // The conversations whose responses haven't been seen yet
+    pending: HashMap<(u64, u64), (String, String)>,
+}
+
+impl IdempotencyCache {
+    pub fn new() -> IdempotencyCache {
+        IdempotencyCache::default()
+    }
+
+    pub fn admit(&mut self, msg: &NodeFromUiMessage, now: Instant) -> Admission {
+        let (context_id, payload, key) = match idempotency_key(&msg.body) {
+            Some(keyed) => keyed,
+            None => return Admission::Fresh,
+        };
+        self.forget_expired(now);
+        let cache_key = (msg.body.opcode.clone(), key);
+        let answer = match self.requests.get(&cache_key) {
+            None => {
+                if self.requests.len() >= MAX_IDEMPOTENCY_KEYS {
+                    self.forget_oldest();
+                }
+                self.requests.insert(
+                    cache_key.clone(),
+                    Request {
+                        payload,
+                        response_opt: None,
+                        expires: now + IDEMPOTENCY_KEY_LIFETIME,
+                    },
+                );
+                self.pending.insert((msg.client_id, context_id), cache_key);
+                return Admission::Fresh;
+            }
+            Some(request) if request.payload != payload => Err((
+                DUPLICATE_REQUEST_ERROR,
+                format!(
+                    "Idempotency key '{}' was already used for a different '{}' request",
+                    cache_key.1, cache_key.0
+                ),
+            )),
+            Some(request) => match &request.response_opt {
+                Some(response) => response.clone(),
+                None => Err((
+                    DUPLICATE_REQUEST_ERROR,
+                    format!(
+                        "The '{}' request with idempotency key '{}' is still being handled",
+                        cache_key.0, cache_key.1
+                    ),
+                )),
+            },
+        };
+        Admission::Answered(NodeToUiMessage {
+            target: MessageTarget::ClientId(msg.client_id),
+            body: MessageBody {
+                opcode: msg.body.opcode.clone(),
+                path: Conversation(context_id),
+                payload: answer,
+            },
+        })
+    }
+
+    pub fn record(&mut self, msg: &NodeToUiMessage) {
+        let conversation = match (&msg.target, &msg.body.path) {
+            (MessageTarget::ClientId(client_id), Conversation(context_id)) => {
+                (*client_id, *context_id)
+            }
+            _ => return,
+        };
+        let cache_key = match self.pending.remove(&conversation) {
+            Some(cache_key) => cache_key,
+            None => return,
+        };
+        if let Some(request) = self.requests.get_mut(&cache_key) {
+            request.response_opt = Some(msg.body.payload.clone());
+        }
+    }
+
+    fn forget_expired(&mut self, now: Instant) {
+        self.requests.retain(|_, request| request.expires > now);
+        let requests = &self.requests;
+        self.pending
+            .retain(|_, cache_key| requests.contains_key(cache_key));
+    }
+
+    fn forget_oldest(&mut self) {
+        let oldest_opt = self
+            .requests
+            .iter()
+            .min_by_key(|(_, request)| request.expires)
+            .map(|(cache_key, _)| cache_key.clone());
+        if let Some(oldest) = oldest_opt {
+            self.requests.remove(&oldest);
+            self.pending.retain(|_, cache_key| cache_key != &oldest);
+        }
+    }
+}
+
+// Only conversations can be answered, so a key on anything else is ignored
+fn idempotency_key(body: &MessageBody) -> Option<(u64, Value, String)> {
+    let context_id = match body.path {
+        Conversation(context_id) if takes_idempotency_key(&body.opcode) => context_id,
+        _ => return None,
+    };
+    let payload: Value = match &body.payload {
+        Ok(json) => serde_json::from_str(json).ok()?,
+        Err(_) => return None,
+    };
+    let key = payload.get(IDEMPOTENCY_KEY_FIELD)?.as_str()?.to_string();
+    Some((context_id, payload, key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use masq_lib::messages::{ToMessageBody, UiShutdownResponse};
+    use masq_lib::ui_gateway::MessagePath::FireAndForget;
+
+    fn keyed_request(
+        client_id: u64,
+        context_id: u64,
+        opcode: &str,
+        payload: &str,
+    ) -> NodeFromUiMessage {
+        NodeFromUiMessage {
+            client_id,
+            body: MessageBody {
+                opcode: opcode.to_string(),
+                path: Conversation(context_id),
+                payload: Ok(payload.to_string()),
+            },
+        }
+    }
+
+    fn shutdown_request(client_id: u64, context_id: u64) -> NodeFromUiMessage {
+        keyed_request(
+            client_id,
+            context_id,
+            "shutdown",
+            r#"{"idempotencyKey": "bye-1"}"#,
+        )
+    }
+
+    #[test]
+    fn a_retried_request_gets_the_first_response_instead_of_being_applied_again() {
+        let now = Instant::now();
+        let mut subject = IdempotencyCache::new();
+        let first = subject.admit(&shutdown_request(1234, 10), now);
+        subject.record(&NodeToUiMessage {
+            target: MessageTarget::ClientId(1234),
+            body: UiShutdownResponse {}.tmb(10),
+        });
+
+        let retry = subject.admit(&shutdown_request(2345, 20), now);
+
+        assert_eq!(first, Admission::Fresh);
+        assert_eq!(
+            retry,
+            Admission::Answered(NodeToUiMessage {
+                target: MessageTarget::ClientId(2345),
+                body: UiShutdownResponse {}.tmb(20),
+            })
+        );
+    }
+
+    #[test]
+    fn a_request_retried_before_it_has_been_answered_is_refused() {
+        let now = Instant::now();
+        let mut subject = IdempotencyCache::new();
+        subject.admit(&shutdown_request(1234, 10), now);
+        subject.record(&NodeToUiMessage {
+            target: MessageTarget::ClientId(1234),
+            body: UiShutdownResponse {}.tmb(11),
+        });
+
+        let result = subject.admit(&shutdown_request(2345, 20), now);
+
+        assert_eq!(
+            result,
+            Admission::Answered(NodeToUiMessage {
+                target: MessageTarget::ClientId(2345),
+                body: MessageBody {
+                    opcode: "shutdown".to_string(),
+                    path: Conversation(20),
+                    payload: Err((
+                        DUPLICATE_REQUEST_ERROR,
+                        "The 'shutdown' request with idempotency key 'bye-1' is still being handled"
+                            .to_string()
+                    )),
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn a_key_reused_for_a_different_request_is_refused() {
+        let now = Instant::now();
+        let mut subject = IdempotencyCache::new();
+        subject.admit(
+            &keyed_request(
+                1234,
+                10,
+                "setup",
+                r#"{"idempotencyKey": "k", "values": [{"name": "ip", "value": "1.2.3.4"}]}"#,
+            ),
+            now,
+        );
+
+        let same = subject.admit(
+            &keyed_request(
+                1234,
+                11,
+                "setup",
+                r#"{"values": [{"value": "1.2.3.4", "name": "ip"}], "idempotencyKey": "k"}"#,
+            ),
+            now,
+        );
+        let different = subject.admit(
+            &keyed_request(
+                1234,
+                12,
+                "setup",
+                r#"{"idempotencyKey": "k", "values": [{"name": "ip", "value": "4.3.2.1"}]}"#,
+            ),
+            now,
+        );
+
+        match same {
+            Admission::Answered(NodeToUiMessage { body, .. }) => assert_eq!(
+                body.payload.err().unwrap().1,
+                "The 'setup' request with idempotency key 'k' is still being handled".to_string()
+            ),
+            x => panic!("Expected an answer, got {:?}", x),
+        }
+        match different {
+            Admission::Answered(NodeToUiMessage { body, .. }) => assert_eq!(
+                body.payload.err(),
+                Some((
+                    DUPLICATE_REQUEST_ERROR,
+                    "Idempotency key 'k' was already used for a different 'setup' request"
+                        .to_string()
+                ))
+            ),
+            x => panic!("Expected an answer, got {:?}", x),
+        }
+    }
+
+    #[test]
+    fn keys_are_forgotten_when_they_expire_and_ignored_where_they_dont_belong() {
+        let now = Instant::now();
+        let mut subject = IdempotencyCache::new();
+        subject.admit(&shutdown_request(1234, 10), now);
+        let fire_and_forget = NodeFromUiMessage {
+            client_id: 1234,
+            body: MessageBody {
+                path: FireAndForget,
+                ..shutdown_request(1234, 10).body
+            },
+        };
+        let read_only = keyed_request(1234, 11, "financials", r#"{"idempotencyKey": "k"}"#);
+
+        let after_expiry =
+            subject.admit(&shutdown_request(2345, 20), now + IDEMPOTENCY_KEY_LIFETIME);
+
+        assert_eq!(after_expiry, Admission::Fresh);
+        assert_eq!(subject.admit(&fire_and_forget, now), Admission::Fresh);
+        assert_eq!(subject.admit(&read_only, now), Admission::Fresh);
+        assert_eq!(subject.admit(&read_only, now), Admission::Fresh);
+    }
+}
@@ -1,16 +1,29 @@
 // Copyright (c) 2017-2018, Substratum LLC (https://substratum.net) and/or its affiliates. All rights reserved.
 
+mod authenticator;
+mod grpc_facade;
+mod grpc_schema;
+mod health;
+mod idempotency;
+mod rest_facade;
+mod subscriptions;
+mod tls;
 mod websocket_supervisor;
 
 #[cfg(test)]
 pub mod websocket_supervisor_mock;
 
 use crate::daemon::DaemonBindMessage;
-use crate::sub_lib::logger::Logger;
+use crate::sub_lib::logger::{set_ui_log_sub, Logger};
 use crate::sub_lib::peer_actors::BindMessage;
-use crate::sub_lib::ui_gateway::UiGatewayConfig;
 use crate::sub_lib::ui_gateway::UiGatewaySubs;
+use crate::sub_lib::ui_gateway::{UiAuthConfig, UiGatewayConfig, UiTlsConfig};
 use crate::sub_lib::utils::NODE_MAILBOX_CAPACITY;
+use crate::ui_gateway::grpc_facade::GrpcFacade;
+use crate::ui_gateway::grpc_schema::{GrpcMethod, DAEMON_METHODS, NODE_METHODS};
+use crate::ui_gateway::health::{start_health_server, HealthChecker};
+use crate::ui_gateway::idempotency::{Admission, IdempotencyCache};
+use crate::ui_gateway::rest_facade::RestFacade;
 use crate::ui_gateway::websocket_supervisor::WebSocketSupervisor;
 use crate::ui_gateway::websocket_supervisor::WebSocketSupervisorReal;
 use actix::Actor;
@@ -18,14 +31,34 @@ use actix::Addr;
 use actix::Context;
 use actix::Handler;
 use actix::Recipient;
-use masq_lib::ui_gateway::{NodeFromUiMessage, NodeToUiMessage};
+use masq_lib::messages::{
+    FromMessageBody, ToMessageBody, UiEffectiveConfig, UiEffectiveConfigBroadcast,
+    UiEffectiveConfigRequest, UiEffectiveConfigResponse, UiHealthRequest,
+};
+use masq_lib::ui_gateway::{MessageTarget, NodeFromUiMessage, NodeToUiMessage};
+use std::path::PathBuf;
+use std::time::Instant;
 
 pub const CRASH_KEY: &str = "UIGATEWAY";
 
 pub struct UiGateway {
     port: u16,
+    tls_opt: Option<UiTlsConfig>,
+    auth_opt: Option<UiAuthConfig>,
+    rest_port_opt: Option<u16>,
+    grpc_port_opt: Option<u16>,
+    health_port_opt: Option<u16>,
+    data_directory: PathBuf,
     websocket_supervisor: Option<Box<dyn WebSocketSupervisor>>,
+    rest_facade_opt: Option<RestFacade>,
+    grpc_facade_opt: Option<GrpcFacade>,
+    // Only a Node has one; the Daemon passes health requests along like anything else
+    health_checker_opt: Option<HealthChecker>,
+    to_ui_message_sub_opt: Option<Recipient<NodeToUiMessage>>,
     incoming_message_recipients: Vec<Recipient<NodeFromUiMessage>>,
+    idempotency_cache: IdempotencyCache,
+    // What the Node broadcast about itself when it started, for UIs that connect afterward
+    effective_config_opt: Option<UiEffectiveConfig>,
     logger: Logger,
 }
 
@@ -33,12 +66,62 @@ impl UiGateway {
     pub fn new(config: &UiGatewayConfig) -> UiGateway {
         UiGateway {
             port: config.ui_port,
+            tls_opt: config.tls_opt.clone(),
+            auth_opt: config.auth_opt.clone(),
+            rest_port_opt: config.rest_port_opt,
+            grpc_port_opt: config.grpc_port_opt,
+            health_port_opt: config.health_port_opt,
+            data_directory: config.data_directory.clone(),
             websocket_supervisor: None,
+            rest_facade_opt: None,
+            grpc_facade_opt: None,
+            health_checker_opt: None,
+            to_ui_message_sub_opt: None,
             incoming_message_recipients: vec![],
+            idempotency_cache: IdempotencyCache::new(),
+            effective_config_opt: None,
             logger: Logger::new("UiGateway"),
         }
     }
 
+    fn start_grpc_facade(
+        &self,
+        methods: &'static [&'static GrpcMethod],
+        from_ui_message_sub: Recipient<NodeFromUiMessage>,
+    ) -> Option<GrpcFacade> {
+        self.grpc_port_opt.map(|grpc_port| {
+            GrpcFacade::start(
+                grpc_port,
+                methods,
+                self.auth_opt.clone(),
+                from_ui_message_sub,
+            )
+            .unwrap_or_else(|e| panic!("Couldn't start GrpcFacade: {:?}", e))
+        })
+    }
+
+    fn send_to_ui(&self, msg: NodeToUiMessage) {
+        // gRPC client IDs are above the REST facade's, so gRPC has to get first pick
+        let msg = match &self.grpc_facade_opt {
+            Some(grpc_facade) => match grpc_facade.claim(msg) {
+                Some(msg) => msg,
+                None => return,
+            },
+            None => msg,
+        };
+        let msg = match &self.rest_facade_opt {
+            Some(rest_facade) => match rest_facade.claim(msg) {
+                Some(msg) => msg,
+                None => return,
+            },
+            None => msg,
+        };
+        self.websocket_supervisor
+            .as_ref()
+            .expect("WebsocketSupervisor is unbound")
+            .send_msg(msg)
+    }
+
     pub fn make_subs_from(addr: &Addr<UiGateway>) -> UiGatewaySubs {
         UiGatewaySubs {
             bind: recipient!(addr, BindMessage),
@@ -61,9 +144,58 @@ impl Handler<BindMessage> for UiGateway {
             msg.peer_actors.neighborhood.from_ui_message_sub.clone(),
             msg.peer_actors.blockchain_bridge.ui_sub.clone(),
             msg.peer_actors.dispatcher.ui_sub.clone(),
+            msg.peer_actors.proxy_server.from_ui_message_sub.clone(),
         ];
+        set_ui_log_sub(Some(
+            msg.peer_actors.ui_gateway.node_to_ui_message_sub.clone(),
+        ));
+        self.rest_facade_opt = self.rest_port_opt.map(|rest_port| {
+            RestFacade::start(
+                rest_port,
+                self.auth_opt.clone(),
+                msg.peer_actors.ui_gateway.node_from_ui_message_sub.clone(),
+            )
+            .unwrap_or_else(|e| panic!("Couldn't start RestFacade: {:?}", e))
+        });
+        self.grpc_facade_opt = self.start_grpc_facade(
+            NODE_METHODS,
+            msg.peer_actors.ui_gateway.node_from_ui_message_sub.clone(),
+        );
+        let health_checker = HealthChecker::new(
+            vec![
+                (
+                    "Accountant",
+                    msg.peer_actors.accountant.ui_message_sub.clone(),
+                ),
+                (
+                    "Neighborhood",
+                    msg.peer_actors.neighborhood.from_ui_message_sub.clone(),
+                ),
+                (
+                    "BlockchainBridge",
+                    msg.peer_actors.blockchain_bridge.ui_sub.clone(),
+                ),
+                ("Dispatcher", msg.peer_actors.dispatcher.ui_sub.clone()),
+                (
+                    "ProxyServer",
+                    msg.peer_actors.proxy_server.from_ui_message_sub.clone(),
+                ),
+            ],
+            self.data_directory.clone(),
+        );
+        if let Some(health_port) = self.health_port_opt {
+            start_health_server(health_port, health_checker.clone())
+                .unwrap_or_else(|e| panic!("Couldn't start health server: {:?}", e));
+        }
+        #[cfg(unix)]
+        crate::ui_gateway::health::start_systemd_watchdog(health_checker.clone());
+        self.health_checker_opt = Some(health_checker);
+        self.to_ui_message_sub_opt =
+            Some(msg.peer_actors.ui_gateway.node_to_ui_message_sub.clone());
         self.websocket_supervisor = match WebSocketSupervisorReal::new(
             self.port,
+            self.tls_opt.clone(),
+            self.auth_opt.clone(),
             msg.peer_actors.ui_gateway.node_from_ui_message_sub,
         ) {
             Ok(wss) => Some(Box::new(wss)),
@@ -79,11 +211,17 @@ impl Handler<DaemonBindMessage> for UiGateway {
     fn handle(&mut self, msg: DaemonBindMessage, ctx: &mut Self::Context) -> Self::Result {
         ctx.set_mailbox_capacity(NODE_MAILBOX_CAPACITY);
         self.incoming_message_recipients = msg.from_ui_message_recipients;
-        self.websocket_supervisor =
-            match WebSocketSupervisorReal::new(self.port, msg.from_ui_message_recipient) {
-                Ok(wss) => Some(Box::new(wss)),
-                Err(e) => panic!("Couldn't start WebSocketSupervisor: {:?}", e),
-            };
+        self.grpc_facade_opt =
+            self.start_grpc_facade(DAEMON_METHODS, msg.from_ui_message_recipient.clone());
+        self.websocket_supervisor = match WebSocketSupervisorReal::new(
+            self.port,
+            self.tls_opt.clone(),
+            self.auth_opt.clone(),
+            msg.from_ui_message_recipient,
+        ) {
+            Ok(wss) => Some(Box::new(wss)),
+            Err(e) => panic!("Couldn't start WebSocketSupervisor: {:?}", e),
+        };
         debug!(self.logger, "UIGateway bound");
     }
 }
@@ -92,10 +230,11 @@ impl Handler<NodeToUiMessage> for UiGateway {
     type Result = ();
 
     fn handle(&mut self, msg: NodeToUiMessage, _ctx: &mut Self::Context) -> Self::Result {
-        self.websocket_supervisor
-            .as_ref()
-            .expect("WebsocketSupervisor is unbound")
-            .send_msg(msg)
+        self.idempotency_cache.record(&msg);
+        if let Ok((broadcast, _)) = UiEffectiveConfigBroadcast::fmb(msg.body.clone()) {
+            self.effective_config_opt = Some(broadcast.config);
+        }
+        self.send_to_ui(msg)
     }
 }
 
@@ -103,6 +242,46 @@ impl Handler<NodeFromUiMessage> for UiGateway {
     type Result = ();
 
     fn handle(&mut self, msg: NodeFromUiMessage, _ctx: &mut Self::Context) -> Self::Result {
+        if let (Some(health_checker), Some(to_ui_message_sub)) =
+            (&self.health_checker_opt, &self.to_ui_message_sub_opt)
+        {
+            if let Ok((_, context_id)) = UiHealthRequest::fmb(msg.body.clone()) {
+                // The checks wait on the other actors, so they can't run on this one's thread
+                let (health_checker, to_ui_message_sub) =
+                    (health_checker.clone(), to_ui_message_sub.clone());
+                std::thread::spawn(move || {
+                    to_ui_message_sub
+                        .try_send(NodeToUiMessage {
+                            target: MessageTarget::ClientId(msg.client_id),
+                            body: health_checker.check().tmb(context_id),
+                        })
+                        .expect("UiGateway is dead")
+                });
+                return;
+            }
+        }
+        if let Some(config) = &self.effective_config_opt {
+            if let Ok((_, context_id)) = UiEffectiveConfigRequest::fmb(msg.body.clone()) {
+                self.send_to_ui(NodeToUiMessage {
+                    target: MessageTarget::ClientId(msg.client_id),
+                    body: UiEffectiveConfigResponse {
+                        config: config.clone(),
+                    }
+                    .tmb(context_id),
+                });
+                return;
+            }
+        }
+        if let Admission::Answered(response) = self.idempotency_cache.admit(&msg, Instant::now()) {
+            debug!(
+                self.logger,
+                "Answering repeated '{}' request from client {} without applying it again",
+                msg.body.opcode,
+                msg.client_id
+            );
+            self.send_to_ui(response);
+            return;
+        }
         let len = self.incoming_message_recipients.len();
         (0..len).for_each(|idx| {
             let recipient = &self.incoming_message_recipients[idx];
@@ -118,11 +297,16 @@ mod tests {
     use super::*;
     use crate::test_utils::recorder::make_recorder;
     use crate::test_utils::recorder::peer_actors_builder;
+    use crate::test_utils::wait_for;
+    use crate::ui_gateway::rest_facade::REST_CLIENT_ID_BASE;
     use crate::ui_gateway::websocket_supervisor_mock::WebSocketSupervisorMock;
     use actix::System;
-    use masq_lib::ui_gateway::MessagePath::FireAndForget;
-    use masq_lib::ui_gateway::{MessageBody, MessageTarget};
+    use masq_lib::messages::{UiHealthResponse, UiShutdownResponse};
+    use masq_lib::test_utils::utils::ensure_node_home_directory_exists;
+    use masq_lib::ui_gateway::MessageBody;
+    use masq_lib::ui_gateway::MessagePath::{Conversation, FireAndForget};
     use masq_lib::utils::find_free_port;
+    use std::sync::mpsc;
     use std::sync::Arc;
     use std::sync::Mutex;
 
@@ -133,6 +317,12 @@ mod tests {
         let subject = UiGateway::new(&UiGatewayConfig {
             ui_port: find_free_port(),
             node_descriptor: String::from(""),
+            tls_opt: None,
+            auth_opt: None,
+            rest_port_opt: None,
+            grpc_port_opt: None,
+            health_port_opt: None,
+            data_directory: PathBuf::new(),
         });
         let system = System::new("test");
         let subject_addr: Addr<UiGateway> = subject.start();
@@ -166,6 +356,212 @@ mod tests {
         );
     }
 
+    #[test]
+    fn health_request_is_answered_by_the_ui_gateway_instead_of_disseminated() {
+        let data_directory = ensure_node_home_directory_exists(
+            "ui_gateway",
+            "health_request_is_answered_by_the_ui_gateway_instead_of_disseminated",
+        );
+        let (accountant, _, accountant_recording_arc) = make_recorder();
+        let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let system =
+                System::new("health_request_is_answered_by_the_ui_gateway_instead_of_disseminated");
+            let subject = UiGateway::new(&UiGatewayConfig {
+                ui_port: find_free_port(),
+                node_descriptor: String::from(""),
+                tls_opt: None,
+                auth_opt: None,
+                rest_port_opt: None,
+                grpc_port_opt: None,
+                health_port_opt: None,
+                data_directory,
+            });
+            let subject_addr: Addr<UiGateway> = subject.start();
+            let peer_actors = peer_actors_builder()
+                .accountant(accountant)
+                .ui_gateway(ui_gateway)
+                .build();
+            subject_addr.try_send(BindMessage { peer_actors }).unwrap();
+            tx.send(subject_addr).unwrap();
+            system.run();
+        });
+        let subject_addr = rx.recv().unwrap();
+
+        subject_addr
+            .try_send(NodeFromUiMessage {
+                client_id: 1234,
+                body: UiHealthRequest {}.tmb(4321),
+            })
+            .unwrap();
+
+        wait_for(None, None, || {
+            !ui_gateway_recording_arc.lock().unwrap().is_empty()
+        });
+        let ui_gateway_recording = ui_gateway_recording_arc.lock().unwrap();
+        let response = ui_gateway_recording.get_record::<NodeToUiMessage>(0);
+        assert_eq!(response.target, MessageTarget::ClientId(1234));
+        let (body, context_id) = UiHealthResponse::fmb(response.body.clone()).unwrap();
+        assert_eq!(context_id, 4321);
+        assert_eq!(body.healthy, false); // there's no database in the data directory
+        assert_eq!(
+            body.checks
+                .iter()
+                .map(|check| check.name.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["actors", "database", "neighborhood", "routes"]
+        );
+        let accountant_recording = accountant_recording_arc.lock().unwrap();
+        assert_eq!(
+            accountant_recording
+                .get_record::<NodeFromUiMessage>(0)
+                .body
+                .opcode,
+            "healthProbe"
+        );
+        assert_eq!(accountant_recording.len(), 1);
+    }
+
+    #[test]
+    fn repeated_request_with_an_idempotency_key_is_answered_without_being_disseminated_again() {
+        let (accountant, _, accountant_recording_arc) = make_recorder();
+        let send_msg_parameters_arc = Arc::new(Mutex::new(vec![]));
+        let thread_send_msg_parameters_arc = send_msg_parameters_arc.clone();
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let system = System::new("test");
+            let mut subject = UiGateway::new(&UiGatewayConfig {
+                ui_port: find_free_port(),
+                node_descriptor: String::from(""),
+                tls_opt: None,
+                auth_opt: None,
+                rest_port_opt: None,
+                grpc_port_opt: None,
+                health_port_opt: None,
+                data_directory: PathBuf::new(),
+            });
+            subject.websocket_supervisor = Some(Box::new(
+                WebSocketSupervisorMock::new().send_msg_parameters(&thread_send_msg_parameters_arc),
+            ));
+            subject.incoming_message_recipients =
+                vec![accountant.start().recipient::<NodeFromUiMessage>()];
+            tx.send(subject.start()).unwrap();
+            system.run();
+        });
+        let subject_addr: Addr<UiGateway> = rx.recv().unwrap();
+        let request = |client_id, context_id| NodeFromUiMessage {
+            client_id,
+            body: MessageBody {
+                opcode: "shutdown".to_string(),
+                path: Conversation(context_id),
+                payload: Ok(r#"{"idempotencyKey": "bye-1"}"#.to_string()),
+            },
+        };
+
+        subject_addr.try_send(request(1234, 10)).unwrap();
+        subject_addr
+            .try_send(NodeToUiMessage {
+                target: MessageTarget::ClientId(1234),
+                body: UiShutdownResponse {}.tmb(10),
+            })
+            .unwrap();
+        subject_addr.try_send(request(2345, 20)).unwrap();
+
+        wait_for(None, None, || {
+            send_msg_parameters_arc.lock().unwrap().len() == 2
+                && !accountant_recording_arc.lock().unwrap().is_empty()
+        });
+        let accountant_recording = accountant_recording_arc.lock().unwrap();
+        assert_eq!(
+            accountant_recording.get_record::<NodeFromUiMessage>(0),
+            &request(1234, 10)
+        );
+        assert_eq!(accountant_recording.len(), 1);
+        let send_parameters = send_msg_parameters_arc.lock().unwrap();
+        assert_eq!(
+            *send_parameters,
+            vec![
+                NodeToUiMessage {
+                    target: MessageTarget::ClientId(1234),
+                    body: UiShutdownResponse {}.tmb(10),
+                },
+                NodeToUiMessage {
+                    target: MessageTarget::ClientId(2345),
+                    body: UiShutdownResponse {}.tmb(20),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn effective_config_broadcast_is_remembered_and_answers_later_requests() {
+        let (accountant, _, accountant_recording_arc) = make_recorder();
+        let send_msg_parameters_arc = Arc::new(Mutex::new(vec![]));
+        let websocket_supervisor =
+            WebSocketSupervisorMock::new().send_msg_parameters(&send_msg_parameters_arc);
+        let mut subject = UiGateway::new(&UiGatewayConfig {
+            ui_port: find_free_port(),
+            node_descriptor: String::from(""),
+            tls_opt: None,
+            auth_opt: None,
+            rest_port_opt: None,
+            grpc_port_opt: None,
+            health_port_opt: None,
+            data_directory: PathBuf::new(),
+        });
+        let system = System::new("test");
+        subject.websocket_supervisor = Some(Box::new(websocket_supervisor));
+        subject.incoming_message_recipients =
+            vec![accountant.start().recipient::<NodeFromUiMessage>()];
+        let subject_addr: Addr<UiGateway> = subject.start();
+        let config = UiEffectiveConfig {
+            chain: "ropsten".to_string(),
+            chain_id: 3,
+            clandestine_port_opt: Some(1234),
+            ui_port: 5333,
+            dns_port: 53,
+            socks_port_opt: None,
+            metrics_port_opt: None,
+            neighborhood_mode: "Standard".to_string(),
+            has_consuming_wallet: false,
+            has_earning_wallet: true,
+            node_version: "1.2.3".to_string(),
+            schema_version: "0.0.17".to_string(),
+        };
+        let broadcast = NodeToUiMessage {
+            target: MessageTarget::AllClients,
+            body: UiEffectiveConfigBroadcast {
+                config: config.clone(),
+            }
+            .tmb(0),
+        };
+
+        subject_addr.try_send(broadcast.clone()).unwrap();
+        subject_addr
+            .try_send(NodeFromUiMessage {
+                client_id: 1234,
+                body: UiEffectiveConfigRequest {}.tmb(10),
+            })
+            .unwrap();
+
+        System::current().stop();
+        system.run();
+        let accountant_recording = accountant_recording_arc.lock().unwrap();
+        assert_eq!(accountant_recording.len(), 0);
+        let send_parameters = send_msg_parameters_arc.lock().unwrap();
+        assert_eq!(
+            *send_parameters,
+            vec![
+                broadcast,
+                NodeToUiMessage {
+                    target: MessageTarget::ClientId(1234),
+                    body: UiEffectiveConfigResponse { config }.tmb(10),
+                },
+            ]
+        );
+    }
+
     #[test]
     fn outbound_ui_message_goes_only_to_websocket_supervisor() {
         let (accountant, _, accountant_recording_arc) = make_recorder();
@@ -175,6 +571,12 @@ mod tests {
         let mut subject = UiGateway::new(&UiGatewayConfig {
             ui_port: find_free_port(),
             node_descriptor: String::from(""),
+            tls_opt: None,
+            auth_opt: None,
+            rest_port_opt: None,
+            grpc_port_opt: None,
+            health_port_opt: None,
+            data_directory: PathBuf::new(),
         });
         let system = System::new("test");
         subject.websocket_supervisor = Some(Box::new(websocket_supervisor));
@@ -199,4 +601,53 @@ mod tests {
         let send_parameters = send_msg_parameters_arc.lock().unwrap();
         assert_eq!(send_parameters[0], msg);
     }
+
+    #[test]
+    fn outbound_responses_to_rest_clients_skip_the_websocket_supervisor() {
+        let (accountant, _, _) = make_recorder();
+        let send_msg_parameters_arc = Arc::new(Mutex::new(vec![]));
+        let websocket_supervisor =
+            WebSocketSupervisorMock::new().send_msg_parameters(&send_msg_parameters_arc);
+        let mut subject = UiGateway::new(&UiGatewayConfig {
+            ui_port: find_free_port(),
+            node_descriptor: String::from(""),
+            tls_opt: None,
+            auth_opt: None,
+            rest_port_opt: None,
+            grpc_port_opt: None,
+            health_port_opt: None,
+            data_directory: PathBuf::new(),
+        });
+        let system = System::new("test");
+        subject.websocket_supervisor = Some(Box::new(websocket_supervisor));
+        subject.rest_facade_opt = Some(
+            RestFacade::start(
+                find_free_port(),
+                None,
+                accountant.start().recipient::<NodeFromUiMessage>(),
+            )
+            .unwrap(),
+        );
+        let subject_addr: Addr<UiGateway> = subject.start();
+        let rest_msg = NodeToUiMessage {
+            target: MessageTarget::ClientId(REST_CLIENT_ID_BASE),
+            body: MessageBody {
+                opcode: "booga".to_string(),
+                path: Conversation(0),
+                payload: Ok("{}".to_string()),
+            },
+        };
+        let websocket_msg = NodeToUiMessage {
+            target: MessageTarget::ClientId(1234),
+            ..rest_msg.clone()
+        };
+
+        subject_addr.try_send(rest_msg).unwrap();
+        subject_addr.try_send(websocket_msg.clone()).unwrap();
+
+        System::current().stop();
+        system.run();
+        let send_parameters = send_msg_parameters_arc.lock().unwrap();
+        assert_eq!(*send_parameters, vec![websocket_msg]);
+    }
 }
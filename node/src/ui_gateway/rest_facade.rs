@@ -0,0 +1,633 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use crate::sub_lib::logger::Logger;
+use crate::sub_lib::ui_gateway::UiAuthConfig;
+use crate::ui_gateway::authenticator::{AuthenticationFailure, UiAuthenticator};
+use actix::Recipient;
+use masq_lib::ui_gateway::MessagePath::Conversation;
+use masq_lib::ui_gateway::MessageTarget::ClientId;
+use masq_lib::ui_gateway::{MessageBody, NodeFromUiMessage, NodeToUiMessage};
+use masq_lib::utils::localhost;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::io;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc;
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// WebSocket clients are numbered up from zero; they'll never get this far
+pub const REST_CLIENT_ID_BASE: u64 = 1 << 63;
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(30);
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_REQUEST_SIZE: usize = 65536;
+
+struct RestRoute {
+    method: &'static str,
+    path: &'static str,
+    opcode: &'static str,
+}
+
+const ROUTES: &[RestRoute] = &[
+    RestRoute {
+        method: "GET",
+        path: "/status",
+        opcode: "descriptor",
+    },
+    RestRoute {
+        method: "GET",
+        path: "/health",
+        opcode: "health",
+    },
+    RestRoute {
+        method: "GET",
+        path: "/financials",
+        opcode: "financials",
+    },
+    RestRoute {
+        method: "GET",
+        path: "/settings/application-rules",
+        opcode: "applicationRules",
+    },
+    RestRoute {
+        method: "PUT",
+        path: "/settings/application-rules",
+        opcode: "setApplicationRules",
+    },
+    RestRoute {
+        method: "GET",
+        path: "/settings/split-tunnel-rules",
+        opcode: "splitTunnelRules",
+    },
+    RestRoute {
+        method: "PUT",
+        path: "/settings/split-tunnel-rules",
+        opcode: "setSplitTunnelRules",
+    },
+    RestRoute {
+        method: "POST",
+        path: "/shutdown",
+        opcode: "shutdown",
+    },
+];
+
+struct RestRequest {
+    method: String,
+    path: String,
+    query: String,
+    authorization_opt: Option<String>,
+    body: String,
+}
+
+struct RestResponse {
+    status: &'static str,
+    body: String,
+}
+
+impl RestResponse {
+    fn error(status: &'static str, code_opt: Option<u64>, message: &str) -> RestResponse {
+        let body = match code_opt {
+            Some(code) => json!({ "code": code, "message": message }),
+            None => json!({ "message": message }),
+        };
+        RestResponse {
+            status,
+            body: body.to_string(),
+        }
+    }
+}
+
+struct RestFacadeInner {
+    // Recipients aren't Sync, and the inner part is shared among connection threads
+    from_ui_message_sub: Mutex<Recipient<NodeFromUiMessage>>,
+    authenticator: Mutex<UiAuthenticator>,
+    next_client_id: AtomicU64,
+    waiting_by_client_id: Mutex<HashMap<u64, Sender<MessageBody>>>,
+    logger: Logger,
+}
+
+// Plain HTTP on localhost for scripts that would rather not speak WebSockets. Each request
+// becomes a conversation with the Node under a client ID of its own, and the UiGateway hands
+// the response back here instead of to the WebSocketSupervisor.
+pub struct RestFacade {
+    inner: Arc<RestFacadeInner>,
+}
+
+impl RestFacade {
+    pub fn start(
+        port: u16,
+        auth_opt: Option<UiAuthConfig>,
+        from_ui_message_sub: Recipient<NodeFromUiMessage>,
+    ) -> io::Result<RestFacade> {
+        let listener = TcpListener::bind(SocketAddr::new(localhost(), port))?;
+        let inner = Arc::new(RestFacadeInner {
+            from_ui_message_sub: Mutex::new(from_ui_message_sub),
+            authenticator: Mutex::new(UiAuthenticator::new(auth_opt)),
+            next_client_id: AtomicU64::new(REST_CLIENT_ID_BASE),
+            waiting_by_client_id: Mutex::new(HashMap::new()),
+            logger: Logger::new("RestFacade"),
+        });
+        let local_addr = listener.local_addr()?;
+        info!(
+            inner.logger,
+            "Serving the REST facade at http://{}", local_addr
+        );
+        let inner_c = inner.clone();
+        thread::spawn(move || {
+            listener
+                .incoming()
+                .for_each(|stream_result| match stream_result {
+                    Ok(stream) => {
+                        let inner = inner_c.clone();
+                        thread::spawn(move || {
+                            if let Err(e) = handle_connection(&inner, stream) {
+                                debug!(inner.logger, "Couldn't answer REST request: {}", e);
+                            }
+                        });
+                    }
+                    Err(e) => warning!(inner_c.logger, "Couldn't accept REST connection: {}", e),
+                })
+        });
+        Ok(RestFacade { inner })
+    }
+
+    // Gives back any message that isn't a response to a REST request
+    pub fn claim(&self, msg: NodeToUiMessage) -> Option<NodeToUiMessage> {
+        let client_id = match msg.target {
+            ClientId(client_id) if client_id >= REST_CLIENT_ID_BASE => client_id,
+            _ => return Some(msg),
+        };
+        let waiting_opt = self
+            .inner
+            .waiting_by_client_id
+            .lock()
+            .expect("REST facade is poisoned")
+            .remove(&client_id);
+        match waiting_opt {
+            // The HTTP client may have given up already; nothing to be done about that
+            Some(waiting) => {
+                let _ = waiting.send(msg.body);
+            }
+            None => debug!(
+                self.inner.logger,
+                "Dropping {} response for departed REST client {}", msg.body.opcode, client_id
+            ),
+        }
+        None
+    }
+}
+
+fn handle_connection(inner: &RestFacadeInner, mut stream: TcpStream) -> io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let peer_addr = stream.peer_addr()?;
+    let response = match read_request(&mut stream)? {
+        Some(request) => respond(inner, peer_addr, request),
+        None => RestResponse::error("400 Bad Request", None, "Malformed HTTP request"),
+    };
+    let http = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        response.body.len(),
+        response.body
+    );
+    stream.write_all(http.as_bytes())?;
+    stream.flush()
+}
+
+fn read_request(stream: &mut TcpStream) -> io::Result<Option<RestRequest>> {
+    let mut data = vec![];
+    let mut buf = [0u8; 4096];
+    let head_len = loop {
+        if let Some(offset) = data.windows(4).position(|window| window == b"\r\n\r\n") {
+            break offset + 4;
+        }
+        if data.len() > MAX_REQUEST_SIZE {
+            return Ok(None);
+        }
+        let len = stream.read(&mut buf)?;
+        if len == 0 {
+            return Ok(None);
+        }
+        data.extend_from_slice(&buf[..len]);
+    };
+    let head = String::from_utf8_lossy(&data[..head_len]).to_string();
+    let mut lines = head.lines();
+    let words = lines
+        .next()
+        .unwrap_or("")
+        .split_whitespace()
+        .collect::<Vec<&str>>();
+    let (method, target) = match words.as_slice() {
+        [method, target, ..] => (method.to_string(), target.to_string()),
+        _ => return Ok(None),
+    };
+    let headers = lines
+        .filter_map(|line| {
+            let mut pieces = line.splitn(2, ':');
+            match (pieces.next(), pieces.next()) {
+                (Some(name), Some(value)) => {
+                    Some((name.trim().to_lowercase(), value.trim().to_string()))
+                }
+                _ => None,
+            }
+        })
+        .collect::<HashMap<String, String>>();
+    let content_length = match headers.get("content-length") {
+        None => 0,
+        Some(value) => match value.parse::<usize>() {
+            Ok(length) if length <= MAX_REQUEST_SIZE => length,
+            _ => return Ok(None),
+        },
+    };
+    while data.len() < head_len + content_length {
+        let len = stream.read(&mut buf)?;
+        if len == 0 {
+            return Ok(None);
+        }
+        data.extend_from_slice(&buf[..len]);
+    }
+    let (path, query) = match target.find('?') {
+        Some(offset) => (
+            target[..offset].to_string(),
+            target[offset + 1..].to_string(),
+        ),
+        None => (target, String::new()),
+    };
+    Ok(Some(RestRequest {
+        method,
+        path,
+        query,
+        authorization_opt: headers.get("authorization").cloned(),
+        body: String::from_utf8_lossy(&data[head_len..head_len + content_length]).to_string(),
+    }))
+}
+
+fn respond(inner: &RestFacadeInner, peer_addr: SocketAddr, request: RestRequest) -> RestResponse {
+    let route = match find_route(&request.method, &request.path) {
+        Ok(route) => route,
+        Err(response) => return response,
+    };
+    let password = match &request.authorization_opt {
+        Some(authorization) if authorization.starts_with("Bearer ") => &authorization[7..],
+        _ => "",
+    };
+    let authentication = inner
+        .authenticator
+        .lock()
+        .expect("REST facade is poisoned")
+        .authenticate(peer_addr.ip(), password, Instant::now());
+    match authentication {
+        Ok(permission) if permission.allows(route.opcode) => (),
+        Ok(_) => {
+            return RestResponse::error(
+                "403 Forbidden",
+                None,
+                &format!("A read-only password can't {} {}", route.method, route.path),
+            )
+        }
+        Err(AuthenticationFailure::WrongPassword { attempts_left }) => {
+            return RestResponse::error(
+                "401 Unauthorized",
+                None,
+                &format!(
+                    "Send Authorization: Bearer <ui-password>; {} more wrong passwords will lock this address out",
+                    attempts_left
+                ),
+            )
+        }
+        Err(AuthenticationFailure::LockedOut { seconds_left }) => {
+            return RestResponse::error(
+                "429 Too Many Requests",
+                None,
+                &format!(
+                    "Too many wrong passwords: locked out for {} more seconds",
+                    seconds_left
+                ),
+            )
+        }
+    }
+    let payload = match payload_from(&request) {
+        Ok(payload) => payload,
+        Err(message) => return RestResponse::error("400 Bad Request", None, &message),
+    };
+    converse(inner, route.opcode, payload)
+}
+
+fn find_route(method: &str, path: &str) -> Result<&'static RestRoute, RestResponse> {
+    let path = path.trim_end_matches('/');
+    let mut routes = ROUTES.iter().filter(|route| route.path == path).peekable();
+    if routes.peek().is_none() {
+        return Err(RestResponse::error(
+            "404 Not Found",
+            None,
+            &format!("There's nothing at {}", path),
+        ));
+    }
+    routes.find(|route| route.method == method).ok_or_else(|| {
+        RestResponse::error(
+            "405 Method Not Allowed",
+            None,
+            &format!("{} doesn't take {}", path, method),
+        )
+    })
+}
+
+// A query string becomes a JSON object, with numbers where they look like numbers; a body is
+// taken to be the JSON payload itself.
+fn payload_from(request: &RestRequest) -> Result<String, String> {
+    if !request.body.trim().is_empty() {
+        return match serde_json::from_str::<Value>(&request.body) {
+            Ok(Value::Object(_)) => Ok(request.body.clone()),
+            _ => Err("The body must be a JSON object".to_string()),
+        };
+    }
+    let object = request
+        .query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let mut pieces = pair.splitn(2, '=');
+            let name = pieces.next().unwrap_or("").to_string();
+            let value = pieces.next().unwrap_or("");
+            let value = match value.parse::<u64>() {
+                Ok(number) => Value::from(number),
+                Err(_) => Value::from(value),
+            };
+            (name, value)
+        })
+        .collect::<Map<String, Value>>();
+    Ok(Value::Object(object).to_string())
+}
+
+fn converse(inner: &RestFacadeInner, opcode: &str, payload: String) -> RestResponse {
+    let client_id = inner.next_client_id.fetch_add(1, Ordering::Relaxed);
+    let (tx, rx) = mpsc::channel();
+    inner
+        .waiting_by_client_id
+        .lock()
+        .expect("REST facade is poisoned")
+        .insert(client_id, tx);
+    let sent = inner
+        .from_ui_message_sub
+        .lock()
+        .expect("REST facade is poisoned")
+        .try_send(NodeFromUiMessage {
+            client_id,
+            body: MessageBody {
+                opcode: opcode.to_string(),
+                path: Conversation(0),
+                payload: Ok(payload),
+            },
+        });
+    let received = match sent {
+        Ok(()) => rx.recv_timeout(RESPONSE_TIMEOUT).ok(),
+        Err(_) => None,
+    };
+    inner
+        .waiting_by_client_id
+        .lock()
+        .expect("REST facade is poisoned")
+        .remove(&client_id);
+    match received {
+        Some(body) => match body.payload {
+            Ok(payload) => RestResponse {
+                status: "200 OK",
+                body: payload,
+            },
+            Err((code, message)) => RestResponse::error("400 Bad Request", Some(code), &message),
+        },
+        None => RestResponse::error(
+            "504 Gateway Timeout",
+            None,
+            &format!("The Node didn't answer the {} request", opcode),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::recorder::{make_recorder, RecordAwaiter, Recording};
+    use actix::{Actor, System};
+    use masq_lib::messages::{
+        FromMessageBody, ToMessageBody, UiFinancialsRequest, UiFinancialsResponse,
+        UiRouteFailureBroadcast, UNMARSHAL_ERROR,
+    };
+    use masq_lib::ui_gateway::MessageTarget::AllClients;
+    use masq_lib::utils::find_free_port;
+    use std::sync::mpsc::Receiver;
+
+    fn make_request(method: &str, path: &str, query: &str, body: &str) -> RestRequest {
+        RestRequest {
+            method: method.to_string(),
+            path: path.to_string(),
+            query: query.to_string(),
+            authorization_opt: None,
+            body: body.to_string(),
+        }
+    }
+
+    fn start_subject(
+        auth_opt: Option<UiAuthConfig>,
+    ) -> (u16, RestFacade, Arc<Mutex<Recording>>, RecordAwaiter) {
+        let port = find_free_port();
+        let (ui_gateway, awaiter, recording_arc) = make_recorder();
+        let (tx, rx): (Sender<RestFacade>, Receiver<RestFacade>) = mpsc::channel();
+        thread::spawn(move || {
+            let system = System::new("rest_facade");
+            let from_ui_message_sub = ui_gateway.start().recipient::<NodeFromUiMessage>();
+            tx.send(RestFacade::start(port, auth_opt, from_ui_message_sub).unwrap())
+                .unwrap();
+            system.run();
+        });
+        (port, rx.recv().unwrap(), recording_arc, awaiter)
+    }
+
+    fn send_http(port: u16, request: &str) -> thread::JoinHandle<String> {
+        let request = request.to_string();
+        thread::spawn(move || {
+            let mut stream = TcpStream::connect(SocketAddr::new(localhost(), port)).unwrap();
+            stream.write_all(request.as_bytes()).unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        })
+    }
+
+    #[test]
+    fn routes_are_found_by_path_and_method() {
+        let status = find_route("GET", "/status/").ok().unwrap();
+        let health = find_route("GET", "/health").ok().unwrap();
+        let set_rules = find_route("PUT", "/settings/split-tunnel-rules")
+            .ok()
+            .unwrap();
+        let wrong_method = find_route("DELETE", "/shutdown").err().unwrap();
+        let wrong_path = find_route("GET", "/booga").err().unwrap();
+
+        assert_eq!(status.opcode, "descriptor");
+        assert_eq!(health.opcode, "health");
+        assert_eq!(set_rules.opcode, "setSplitTunnelRules");
+        assert_eq!(wrong_method.status, "405 Method Not Allowed");
+        assert_eq!(wrong_path.status, "404 Not Found");
+    }
+
+    #[test]
+    fn payloads_come_from_query_strings_or_bodies() {
+        let from_query = payload_from(&make_request(
+            "GET",
+            "/financials",
+            "payableMinimumAmount=10&payableMaximumAge=booga",
+            "",
+        ));
+        let from_body = payload_from(&make_request(
+            "PUT",
+            "/settings/split-tunnel-rules",
+            "",
+            r#"{"rules": ["example.com:direct"]}"#,
+        ));
+        let bad_body = payload_from(&make_request("PUT", "/shutdown", "", "[1, 2]"));
+
+        assert_eq!(
+            from_query,
+            Ok(r#"{"payableMaximumAge":"booga","payableMinimumAmount":10}"#.to_string())
+        );
+        assert_eq!(
+            from_body,
+            Ok(r#"{"rules": ["example.com:direct"]}"#.to_string())
+        );
+        assert_eq!(bad_body, Err("The body must be a JSON object".to_string()));
+    }
+
+    #[test]
+    fn requests_become_conversations_and_responses_come_back_over_http() {
+        let (port, subject, recording_arc, awaiter) = start_subject(None);
+
+        let client = send_http(
+            port,
+            "GET /financials?payableMinimumAmount=1&payableMaximumAge=2&receivableMinimumAmount=3&receivableMaximumAge=4 HTTP/1.1\r\n\r\n",
+        );
+        awaiter.await_message_count(1);
+        let request = recording_arc
+            .lock()
+            .unwrap()
+            .get_record::<NodeFromUiMessage>(0)
+            .clone();
+        let broadcast = NodeToUiMessage {
+            target: AllClients,
+            body: UiRouteFailureBroadcast {
+                hostname: "booga.com".to_string(),
+            }
+            .tmb(0),
+        };
+        let unclaimed = subject.claim(broadcast.clone());
+        let claimed = subject.claim(NodeToUiMessage {
+            target: ClientId(REST_CLIENT_ID_BASE),
+            body: UiFinancialsResponse {
+                payables: vec![],
+                total_payable: 1234,
+                receivables: vec![],
+                total_receivable: 4321,
+            }
+            .tmb(0),
+        });
+        let response = client.join().unwrap();
+
+        assert_eq!(request.client_id, REST_CLIENT_ID_BASE);
+        assert_eq!(
+            UiFinancialsRequest::fmb(request.body).unwrap(),
+            (
+                UiFinancialsRequest {
+                    payable_minimum_amount: 1,
+                    payable_maximum_age: 2,
+                    receivable_minimum_amount: 3,
+                    receivable_maximum_age: 4,
+                },
+                0
+            )
+        );
+        assert_eq!(unclaimed, Some(broadcast));
+        assert_eq!(claimed, None);
+        assert_eq!(
+            response.starts_with("HTTP/1.1 200 OK\r\n"),
+            true,
+            "{}",
+            response
+        );
+        assert_eq!(
+            response.ends_with(
+                r#"{"payables":[],"totalPayable":1234,"receivables":[],"totalReceivable":4321}"#
+            ),
+            true,
+            "{}",
+            response
+        );
+    }
+
+    #[test]
+    fn errors_from_the_node_come_back_as_bad_requests() {
+        let (port, subject, _, awaiter) = start_subject(None);
+
+        let client = send_http(port, "GET /financials HTTP/1.1\r\n\r\n");
+        awaiter.await_message_count(1);
+        subject.claim(NodeToUiMessage {
+            target: ClientId(REST_CLIENT_ID_BASE),
+            body: MessageBody {
+                opcode: "financials".to_string(),
+                path: Conversation(0),
+                payload: Err((UNMARSHAL_ERROR, "missing field".to_string())),
+            },
+        });
+        let response = client.join().unwrap();
+
+        assert_eq!(
+            response.starts_with("HTTP/1.1 400 Bad Request\r\n"),
+            true,
+            "{}",
+            response
+        );
+        assert_eq!(
+            response.ends_with(&format!(
+                r#"{{"code":{},"message":"missing field"}}"#,
+                UNMARSHAL_ERROR
+            )),
+            true,
+            "{}",
+            response
+        );
+    }
+
+    #[test]
+    fn passwords_are_required_when_the_node_has_them() {
+        let (port, _subject, recording_arc, _) = start_subject(Some(UiAuthConfig {
+            full_password: "booga".to_string(),
+            read_only_password_opt: Some("agoob".to_string()),
+        }));
+
+        let no_password = send_http(port, "POST /shutdown HTTP/1.1\r\n\r\n")
+            .join()
+            .unwrap();
+        let read_only = send_http(
+            port,
+            "POST /shutdown HTTP/1.1\r\nAuthorization: Bearer agoob\r\n\r\n",
+        )
+        .join()
+        .unwrap();
+
+        assert_eq!(
+            no_password.starts_with("HTTP/1.1 401 Unauthorized\r\n"),
+            true,
+            "{}",
+            no_password
+        );
+        assert_eq!(
+            read_only.starts_with("HTTP/1.1 403 Forbidden\r\n"),
+            true,
+            "{}",
+            read_only
+        );
+        assert_eq!(recording_arc.lock().unwrap().len(), 0);
+    }
+}
@@ -0,0 +1,266 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use itertools::Itertools;
+use log::Level;
+use masq_lib::messages::{
+    FromMessageBody, UiLogsBroadcast, UiNeighborsChangedBroadcast, UiPaymentBroadcast,
+    UiPaymentReceivedBroadcast, UiRouteFailureBroadcast, UiSubscribeRequest,
+};
+use masq_lib::ui_gateway::MessageBody;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+pub const DEFAULT_LOG_SUBSCRIPTION_LEVEL: Level = Level::Info;
+
+// Broadcasts that go only to the clients that have asked for them
+pub fn is_subscribable(opcode: &str) -> bool {
+    [
+        UiLogsBroadcast::type_opcode(),
+        UiNeighborsChangedBroadcast::type_opcode(),
+        UiPaymentBroadcast::type_opcode(),
+        UiPaymentReceivedBroadcast::type_opcode(),
+        UiRouteFailureBroadcast::type_opcode(),
+    ]
+    .contains(&opcode)
+}
+
+struct ClientSubscriptions {
+    opcodes: HashSet<String>,
+    log_level: Level,
+    // None means every target
+    log_targets_opt: Option<HashSet<String>>,
+}
+
+#[derive(Default)]
+pub struct UiSubscriptions {
+    by_client_id: HashMap<u64, ClientSubscriptions>,
+}
+
+impl UiSubscriptions {
+    pub fn new() -> UiSubscriptions {
+        UiSubscriptions::default()
+    }
+
+    // Returns everything the client is subscribed to afterward. Nothing changes if anything is bad.
+    pub fn subscribe(
+        &mut self,
+        client_id: u64,
+        request: &UiSubscribeRequest,
+    ) -> Result<Vec<String>, String> {
+        if let Some(opcode) = request
+            .opcodes
+            .iter()
+            .find(|opcode| !is_subscribable(opcode))
+        {
+            return Err(format!("There are no '{}' events to subscribe to", opcode));
+        }
+        let log_level_opt = request
+            .log_level_opt
+            .as_ref()
+            .map(|name| Level::from_str(name).map_err(|_| format!("'{}' is not a log level", name)))
+            .transpose()?;
+        let subscriptions =
+            self.by_client_id
+                .entry(client_id)
+                .or_insert_with(|| ClientSubscriptions {
+                    opcodes: HashSet::new(),
+                    log_level: DEFAULT_LOG_SUBSCRIPTION_LEVEL,
+                    log_targets_opt: None,
+                });
+        subscriptions
+            .opcodes
+            .extend(request.opcodes.iter().cloned());
+        if let Some(log_level) = log_level_opt {
+            subscriptions.log_level = log_level;
+        }
+        match &request.log_targets_opt {
+            None => (),
+            Some(targets) if targets.is_empty() => subscriptions.log_targets_opt = None,
+            Some(targets) => {
+                subscriptions.log_targets_opt = Some(targets.iter().cloned().collect())
+            }
+        }
+        Ok(self.opcodes_of(client_id))
+    }
+
+    // Returns whatever the client is still subscribed to
+    pub fn unsubscribe(&mut self, client_id: u64, opcodes: &[String]) -> Vec<String> {
+        if let Some(subscriptions) = self.by_client_id.get_mut(&client_id) {
+            if opcodes.is_empty() {
+                subscriptions.opcodes.clear();
+            } else {
+                opcodes.iter().for_each(|opcode| {
+                    subscriptions.opcodes.remove(opcode);
+                });
+            }
+            if subscriptions.opcodes.is_empty() {
+                self.by_client_id.remove(&client_id);
+            }
+        }
+        self.opcodes_of(client_id)
+    }
+
+    pub fn forget(&mut self, client_id: u64) {
+        self.by_client_id.remove(&client_id);
+    }
+
+    pub fn wants(&self, client_id: u64, body: &MessageBody) -> bool {
+        let subscriptions = match self.by_client_id.get(&client_id) {
+            Some(subscriptions) if subscriptions.opcodes.contains(&body.opcode) => subscriptions,
+            _ => return false,
+        };
+        if body.opcode != UiLogsBroadcast::type_opcode() {
+            return true;
+        }
+        match UiLogsBroadcast::fmb(body.clone()) {
+            Ok((broadcast, _)) => {
+                let level_wanted = match Level::from_str(&broadcast.level) {
+                    Ok(level) => level <= subscriptions.log_level,
+                    Err(_) => false,
+                };
+                let target_wanted = match &subscriptions.log_targets_opt {
+                    None => true,
+                    Some(targets) => targets.contains(&broadcast.target),
+                };
+                level_wanted && target_wanted
+            }
+            Err(_) => false,
+        }
+    }
+
+    // The least severe level anybody wants log records at, if anybody wants them at all
+    pub fn log_level_limit(&self) -> Option<Level> {
+        self.by_client_id
+            .values()
+            .filter(|subscriptions| {
+                subscriptions
+                    .opcodes
+                    .contains(UiLogsBroadcast::type_opcode())
+            })
+            .map(|subscriptions| subscriptions.log_level)
+            .max()
+    }
+
+    fn opcodes_of(&self, client_id: u64) -> Vec<String> {
+        match self.by_client_id.get(&client_id) {
+            Some(subscriptions) => subscriptions.opcodes.iter().cloned().sorted().collect(),
+            None => vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use masq_lib::messages::ToMessageBody;
+
+    fn log_record(level: &str, target: &str) -> MessageBody {
+        UiLogsBroadcast {
+            level: level.to_string(),
+            target: target.to_string(),
+            message: "booga".to_string(),
+            timestamp: "2020-01-01T00:00:00.000".to_string(),
+            correlation_id_opt: None,
+        }
+        .tmb(0)
+    }
+
+    fn request(
+        opcodes: &[&str],
+        log_level_opt: Option<&str>,
+        log_targets_opt: Option<&[&str]>,
+    ) -> UiSubscribeRequest {
+        UiSubscribeRequest {
+            opcodes: opcodes.iter().map(|opcode| opcode.to_string()).collect(),
+            log_level_opt: log_level_opt.map(|level| level.to_string()),
+            log_targets_opt: log_targets_opt
+                .map(|targets| targets.iter().map(|target| target.to_string()).collect()),
+        }
+    }
+
+    fn route_failure() -> MessageBody {
+        UiRouteFailureBroadcast {
+            hostname: "booga.com".to_string(),
+        }
+        .tmb(0)
+    }
+
+    #[test]
+    fn subscriptions_accumulate_and_can_be_dropped() {
+        let mut subject = UiSubscriptions::new();
+
+        let first = subject.subscribe(1, &request(&["payment"], None, None));
+        let second = subject.subscribe(1, &request(&["routeFailure"], None, None));
+        let after_unsubscribe = subject.unsubscribe(1, &["payment".to_string()]);
+        let other_client = subject.unsubscribe(2, &[]);
+
+        assert_eq!(first, Ok(vec!["payment".to_string()]));
+        assert_eq!(
+            second,
+            Ok(vec!["payment".to_string(), "routeFailure".to_string()])
+        );
+        assert_eq!(after_unsubscribe, vec!["routeFailure".to_string()]);
+        assert_eq!(other_client, Vec::<String>::new());
+        assert_eq!(subject.wants(1, &route_failure()), true);
+        assert_eq!(subject.wants(2, &route_failure()), false);
+    }
+
+    #[test]
+    fn bad_subscriptions_change_nothing() {
+        let mut subject = UiSubscriptions::new();
+
+        let bad_opcode =
+            subject.subscribe(1, &request(&["routeFailure", "financials"], None, None));
+        let bad_level = subject.subscribe(1, &request(&["logs"], Some("loud"), None));
+
+        assert_eq!(
+            bad_opcode,
+            Err("There are no 'financials' events to subscribe to".to_string())
+        );
+        assert_eq!(bad_level, Err("'loud' is not a log level".to_string()));
+        assert_eq!(subject.wants(1, &route_failure()), false);
+        assert_eq!(subject.log_level_limit(), None);
+    }
+
+    #[test]
+    fn log_records_go_only_to_clients_whose_level_they_reach() {
+        let mut subject = UiSubscriptions::new();
+        subject
+            .subscribe(1, &request(&["logs"], Some("warn"), None))
+            .unwrap();
+        subject
+            .subscribe(2, &request(&["logs"], None, None))
+            .unwrap();
+
+        assert_eq!(subject.wants(1, &log_record("ERROR", "Neighborhood")), true);
+        assert_eq!(subject.wants(1, &log_record("INFO", "Neighborhood")), false);
+        assert_eq!(subject.wants(2, &log_record("INFO", "Neighborhood")), true);
+        assert_eq!(
+            subject.wants(2, &log_record("DEBUG", "Neighborhood")),
+            false
+        );
+        assert_eq!(subject.log_level_limit(), Some(Level::Info));
+        subject.forget(2);
+        assert_eq!(subject.log_level_limit(), Some(Level::Warn));
+    }
+
+    #[test]
+    fn log_records_go_only_to_clients_that_want_their_targets() {
+        let mut subject = UiSubscriptions::new();
+        subject
+            .subscribe(
+                1,
+                &request(&["logs"], None, Some(&["Accountant", "Hopper"])),
+            )
+            .unwrap();
+        subject
+            .subscribe(2, &request(&["logs"], None, Some(&["Hopper"])))
+            .unwrap();
+        subject
+            .subscribe(2, &request(&["logs"], None, Some(&[])))
+            .unwrap();
+
+        assert_eq!(subject.wants(1, &log_record("INFO", "Accountant")), true);
+        assert_eq!(subject.wants(1, &log_record("INFO", "Neighborhood")), false);
+        assert_eq!(subject.wants(2, &log_record("INFO", "Neighborhood")), true);
+    }
+}
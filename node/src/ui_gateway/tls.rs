@@ -0,0 +1,364 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use crate::sub_lib::logger::Logger;
+use crate::sub_lib::ui_gateway::UiTlsConfig;
+use futures::{try_ready, Async, Future, Poll};
+use itertools::Itertools;
+use openssl::asn1::Asn1Time;
+use openssl::bn::{BigNum, MsbOption};
+use openssl::ec::{EcGroup, EcKey};
+use openssl::error::ErrorStack;
+use openssl::hash::MessageDigest;
+use openssl::nid::Nid;
+use openssl::pkey::{PKey, Private};
+use openssl::ssl::{
+    ErrorCode, HandshakeError, MidHandshakeSslStream, SslAcceptor, SslMethod, SslStream,
+    SslVerifyMode,
+};
+use openssl::x509::extension::SubjectAlternativeName;
+use openssl::x509::{X509Name, X509NameBuilder, X509};
+use std::fs;
+use std::fs::OpenOptions;
+use std::io;
+use std::io::{Read, Write};
+use std::path::Path;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpStream;
+
+// The first byte of every TLS ClientHello; no HTTP request starts with it
+pub const TLS_HANDSHAKE_RECORD: u8 = 0x16;
+const SELF_SIGNED_CERTIFICATE_DAYS: u32 = 3650;
+const SELF_SIGNED_COMMON_NAME: &str = "MASQ Node UI";
+
+pub fn make_acceptor(config: &UiTlsConfig, logger: &Logger) -> io::Result<SslAcceptor> {
+    if config.generate && !(config.certificate_path.exists() && config.key_path.exists()) {
+        generate_self_signed(&config.certificate_path, &config.key_path)?;
+        info!(
+            logger,
+            "Generated a self-signed UI certificate in {:?}", config.certificate_path
+        );
+    }
+    let mut certificates = X509::stack_from_pem(&fs::read(&config.certificate_path)?)
+        .map_err(|e| openssl_error(&config.certificate_path, e))?
+        .into_iter();
+    let certificate = certificates.next().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("No certificate found in {:?}", config.certificate_path),
+        )
+    })?;
+    let key = PKey::private_key_from_pem(&fs::read(&config.key_path)?)
+        .map_err(|e| openssl_error(&config.key_path, e))?;
+    let mut builder = SslAcceptor::mozilla_intermediate(SslMethod::tls()).map_err(to_io_error)?;
+    builder.set_certificate(&certificate).map_err(to_io_error)?;
+    for intermediate in certificates {
+        builder
+            .add_extra_chain_cert(intermediate)
+            .map_err(to_io_error)?;
+    }
+    builder.set_private_key(&key).map_err(to_io_error)?;
+    builder
+        .check_private_key()
+        .map_err(|e| openssl_error(&config.key_path, e))?;
+    if let Some(client_ca_path) = &config.client_ca_path_opt {
+        let client_ca_pem = fs::read(client_ca_path)?;
+        for client_ca in
+            X509::stack_from_pem(&client_ca_pem).map_err(|e| openssl_error(client_ca_path, e))?
+        {
+            builder
+                .cert_store_mut()
+                .add_cert(client_ca)
+                .map_err(to_io_error)?;
+        }
+        builder.set_client_ca_list(
+            X509Name::load_client_ca_file(client_ca_path)
+                .map_err(|e| openssl_error(client_ca_path, e))?,
+        );
+        builder.set_verify(SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT);
+        info!(
+            logger,
+            "UI port will accept TLS only from clients with certificates signed by a CA in {:?}",
+            client_ca_path
+        );
+    }
+    let fingerprint = fingerprint(&certificate)?;
+    info!(
+        logger,
+        "UI port is presenting a certificate with SHA-256 fingerprint {}", fingerprint
+    );
+    Ok(builder.build())
+}
+
+pub fn fingerprint(certificate: &X509) -> io::Result<String> {
+    let digest = certificate
+        .digest(MessageDigest::sha256())
+        .map_err(to_io_error)?;
+    Ok(digest.iter().map(|byte| format!("{:02X}", byte)).join(":"))
+}
+
+fn generate_self_signed(certificate_path: &Path, key_path: &Path) -> io::Result<()> {
+    let (certificate, key) = make_self_signed().map_err(to_io_error)?;
+    if let Some(parent) = key_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut key_options = OpenOptions::new();
+    key_options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        key_options.mode(0o600);
+    }
+    key_options
+        .open(key_path)?
+        .write_all(&key.private_key_to_pem_pkcs8().map_err(to_io_error)?)?;
+    if let Some(parent) = certificate_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(certificate_path, certificate.to_pem().map_err(to_io_error)?)
+}
+
+fn make_self_signed() -> Result<(X509, PKey<Private>), ErrorStack> {
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
+    let key = PKey::from_ec_key(EcKey::generate(&group)?)?;
+    let mut name = X509NameBuilder::new()?;
+    name.append_entry_by_nid(Nid::COMMONNAME, SELF_SIGNED_COMMON_NAME)?;
+    let name = name.build();
+    let mut serial_number = BigNum::new()?;
+    serial_number.rand(127, MsbOption::MAYBE_ZERO, false)?;
+    let mut builder = X509::builder()?;
+    builder.set_version(2)?;
+    let serial_number = serial_number.to_asn1_integer()?;
+    builder.set_serial_number(&serial_number)?;
+    builder.set_subject_name(&name)?;
+    builder.set_issuer_name(&name)?;
+    builder.set_pubkey(&key)?;
+    let not_before = Asn1Time::days_from_now(0)?;
+    let not_after = Asn1Time::days_from_now(SELF_SIGNED_CERTIFICATE_DAYS)?;
+    builder.set_not_before(&not_before)?;
+    builder.set_not_after(&not_after)?;
+    let subject_alternative_name = SubjectAlternativeName::new()
+        .dns("localhost")
+        .ip("127.0.0.1")
+        .build(&builder.x509v3_context(None, None))?;
+    builder.append_extension(subject_alternative_name)?;
+    builder.sign(&key, MessageDigest::sha256())?;
+    Ok((builder.build(), key))
+}
+
+fn openssl_error(path: &Path, e: ErrorStack) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Could not read {:?}: {}", path, e),
+    )
+}
+
+fn to_io_error<E: std::error::Error + Send + Sync + 'static>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, e)
+}
+
+// Resolves to the stream along with whether the client opened with a TLS handshake, without
+// consuming anything the handshake or the WebSocket upgrade will need to read.
+pub struct TlsSniffer {
+    stream_opt: Option<TcpStream>,
+}
+
+impl TlsSniffer {
+    pub fn new(stream: TcpStream) -> TlsSniffer {
+        TlsSniffer {
+            stream_opt: Some(stream),
+        }
+    }
+}
+
+impl Future for TlsSniffer {
+    type Item = (TcpStream, bool);
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        let mut first_byte = [0u8; 1];
+        let len = try_ready!(self
+            .stream_opt
+            .as_mut()
+            .expect("TlsSniffer polled after completion")
+            .poll_peek(&mut first_byte));
+        let stream = self.stream_opt.take().expect("TlsSniffer lost its stream");
+        Ok(Async::Ready((
+            stream,
+            len == 1 && first_byte[0] == TLS_HANDSHAKE_RECORD,
+        )))
+    }
+}
+
+pub struct TlsStream<S> {
+    delegate: SslStream<S>,
+}
+
+impl<S: Read + Write> Read for TlsStream<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.delegate.read(buf)
+    }
+}
+
+impl<S: Read + Write> Write for TlsStream<S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.delegate.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.delegate.flush()
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite> AsyncRead for TlsStream<S> {}
+
+impl<S: AsyncRead + AsyncWrite> AsyncWrite for TlsStream<S> {
+    fn shutdown(&mut self) -> Poll<(), io::Error> {
+        match self.delegate.shutdown() {
+            Ok(_) => self.delegate.get_mut().shutdown(),
+            Err(ref e) if e.code() == ErrorCode::WANT_READ || e.code() == ErrorCode::WANT_WRITE => {
+                Ok(Async::NotReady)
+            }
+            Err(e) => Err(e.into_io_error().unwrap_or_else(to_io_error)),
+        }
+    }
+}
+
+enum HandshakeState<S> {
+    Starting(SslAcceptor, S),
+    Handshaking(MidHandshakeSslStream<S>),
+    Finished,
+}
+
+pub struct TlsHandshake<S> {
+    state: HandshakeState<S>,
+}
+
+impl<S> TlsHandshake<S> {
+    pub fn new(acceptor: SslAcceptor, stream: S) -> TlsHandshake<S> {
+        TlsHandshake {
+            state: HandshakeState::Starting(acceptor, stream),
+        }
+    }
+}
+
+impl<S: Read + Write> Future for TlsHandshake<S> {
+    type Item = TlsStream<S>;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        let result = match std::mem::replace(&mut self.state, HandshakeState::Finished) {
+            HandshakeState::Starting(acceptor, stream) => acceptor.accept(stream),
+            HandshakeState::Handshaking(mid_handshake) => mid_handshake.handshake(),
+            HandshakeState::Finished => panic!("TlsHandshake polled after completion"),
+        };
+        match result {
+            Ok(delegate) => Ok(Async::Ready(TlsStream { delegate })),
+            Err(HandshakeError::WouldBlock(mid_handshake)) => {
+                self.state = HandshakeState::Handshaking(mid_handshake);
+                Ok(Async::NotReady)
+            }
+            Err(HandshakeError::Failure(mid_handshake)) => {
+                Err(to_io_error(mid_handshake.into_error()))
+            }
+            Err(HandshakeError::SetupFailure(e)) => Err(to_io_error(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use masq_lib::test_utils::utils::ensure_node_home_directory_exists;
+
+    #[test]
+    fn generated_certificate_is_reused_once_it_exists() {
+        let directory = ensure_node_home_directory_exists(
+            "ui_gateway_tls",
+            "generated_certificate_is_reused_once_it_exists",
+        );
+        let config = UiTlsConfig {
+            certificate_path: directory.join("ui-tls-certificate.pem"),
+            key_path: directory.join("ui-tls-key.pem"),
+            generate: true,
+            interfaces: vec![],
+            allowlist: vec![],
+            client_ca_path_opt: None,
+        };
+        let logger = Logger::new("test");
+
+        make_acceptor(&config, &logger).unwrap();
+        let first_pem = fs::read(&config.certificate_path).unwrap();
+        make_acceptor(&config, &logger).unwrap();
+        let second_pem = fs::read(&config.certificate_path).unwrap();
+
+        assert_eq!(first_pem, second_pem);
+        let certificate = X509::from_pem(&first_pem).unwrap();
+        assert_eq!(fingerprint(&certificate).unwrap().len(), 32 * 3 - 1);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&config.key_path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o077, 0);
+        }
+    }
+
+    #[test]
+    fn missing_user_certificate_is_not_generated() {
+        let directory = ensure_node_home_directory_exists(
+            "ui_gateway_tls",
+            "missing_user_certificate_is_not_generated",
+        );
+        let config = UiTlsConfig {
+            certificate_path: directory.join("nonexistent-certificate.pem"),
+            key_path: directory.join("nonexistent-key.pem"),
+            generate: false,
+            interfaces: vec![],
+            allowlist: vec![],
+            client_ca_path_opt: None,
+        };
+
+        let result = make_acceptor(&config, &Logger::new("test"));
+
+        assert_eq!(result.err().unwrap().kind(), io::ErrorKind::NotFound);
+        assert_eq!(config.certificate_path.exists(), false);
+    }
+
+    #[test]
+    fn missing_client_ca_is_an_error() {
+        let directory =
+            ensure_node_home_directory_exists("ui_gateway_tls", "missing_client_ca_is_an_error");
+        let config = UiTlsConfig {
+            certificate_path: directory.join("ui-tls-certificate.pem"),
+            key_path: directory.join("ui-tls-key.pem"),
+            generate: true,
+            interfaces: vec![],
+            allowlist: vec![],
+            client_ca_path_opt: Some(directory.join("nonexistent-ca.pem")),
+        };
+
+        let result = make_acceptor(&config, &Logger::new("test"));
+
+        assert_eq!(result.err().unwrap().kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn client_ca_is_accepted_when_it_exists() {
+        let directory = ensure_node_home_directory_exists(
+            "ui_gateway_tls",
+            "client_ca_is_accepted_when_it_exists",
+        );
+        let client_ca_path = directory.join("client-ca.pem");
+        generate_self_signed(&client_ca_path, &directory.join("client-ca-key.pem")).unwrap();
+        let config = UiTlsConfig {
+            certificate_path: directory.join("ui-tls-certificate.pem"),
+            key_path: directory.join("ui-tls-key.pem"),
+            generate: true,
+            interfaces: vec![],
+            allowlist: vec![],
+            client_ca_path_opt: Some(client_ca_path),
+        };
+
+        let result = make_acceptor(&config, &Logger::new("test"));
+
+        assert_eq!(result.is_ok(), true);
+    }
+}
@@ -1,5 +1,11 @@
 // Copyright (c) 2017-2018, Substratum LLC (https://substratum.net) and/or its affiliates. All rights reserved.
-use crate::sub_lib::logger::Logger;
+use crate::sub_lib::logger::{set_ui_log_level_limit, Logger};
+use crate::sub_lib::ui_gateway::{UiAuthConfig, UiTlsConfig};
+use crate::ui_gateway::authenticator::{AuthenticationFailure, UiAuthenticator, UiPermission};
+use crate::ui_gateway::subscriptions;
+use crate::ui_gateway::subscriptions::UiSubscriptions;
+use crate::ui_gateway::tls;
+use crate::ui_gateway::tls::{TlsHandshake, TlsSniffer};
 use actix::Recipient;
 use bytes::BytesMut;
 use futures::future::FutureResult;
@@ -10,24 +16,35 @@ use futures::Future;
 use futures::Sink;
 use futures::Stream;
 use itertools::Itertools;
-use masq_lib::messages::{ToMessageBody, UiUnmarshalError, NODE_UI_PROTOCOL, UNMARSHAL_ERROR};
+use masq_lib::ip_network::IpNetwork;
+use masq_lib::messages::{
+    FromMessageBody, ToMessageBody, UiAuthenticateRequest, UiAuthenticateResponse,
+    UiSubscribeRequest, UiSubscribeResponse, UiUnmarshalError, UiUnsubscribeRequest,
+    UiUnsubscribeResponse, AUTHENTICATION_ERROR, NODE_UI_PROTOCOL, PERMISSION_ERROR,
+    SUBSCRIPTION_ERROR, UNMARSHAL_ERROR,
+};
 use masq_lib::ui_gateway::MessagePath::Conversation;
 use masq_lib::ui_gateway::MessageTarget::ClientId;
 use masq_lib::ui_gateway::{MessageBody, MessageTarget, NodeFromUiMessage, NodeToUiMessage};
 use masq_lib::ui_traffic_converter::UiTrafficConverter;
 use masq_lib::ui_traffic_converter::UnmarshalError::{Critical, NonCritical};
 use masq_lib::utils::localhost;
+use openssl::ssl::SslAcceptor;
 use std::any::Any;
 use std::collections::HashMap;
 use std::fmt::Debug;
-use std::net::SocketAddr;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::sync::Mutex;
 use std::sync::{Arc, MutexGuard};
+use std::time::Instant;
+use tokio::net::TcpListener;
 use tokio::reactor::Handle;
 use websocket::client::r#async::Framed;
 use websocket::r#async::MessageCodec;
+use websocket::r#async::Stream as WsStream;
 use websocket::r#async::TcpStream;
 use websocket::server::r#async::Server;
+use websocket::server::upgrade::r#async::IntoWs;
 use websocket::server::upgrade::WsUpgrade;
 use websocket::OwnedMessage;
 use websocket::WebSocketError;
@@ -38,11 +55,11 @@ trait ClientWrapper: Send + Any {
     fn flush(&mut self) -> Result<(), WebSocketError>;
 }
 
-struct ClientWrapperReal {
-    delegate: Wait<SplitSink<Framed<TcpStream, MessageCodec<OwnedMessage>>>>,
+struct ClientWrapperReal<S: WsStream> {
+    delegate: Wait<SplitSink<Framed<S, MessageCodec<OwnedMessage>>>>,
 }
 
-impl ClientWrapper for ClientWrapperReal {
+impl<S: WsStream + Send + 'static> ClientWrapper for ClientWrapperReal<S> {
     fn as_any(&self) -> &dyn Any {
         self
     }
@@ -71,6 +88,19 @@ struct WebSocketSupervisorInner {
     from_ui_message_sub: Recipient<NodeFromUiMessage>,
     client_id_by_socket_addr: HashMap<SocketAddr, u64>,
     client_by_id: HashMap<u64, Box<dyn ClientWrapper>>,
+    authenticator: UiAuthenticator,
+    permission_by_client_id: HashMap<u64, UiPermission>,
+    subscriptions: UiSubscriptions,
+}
+
+impl WebSocketSupervisorInner {
+    // None until the client authenticates, if it has to
+    fn permission_of(&self, client_id: u64) -> Option<UiPermission> {
+        self.permission_by_client_id
+            .get(&client_id)
+            .copied()
+            .or_else(|| self.authenticator.initial_permission())
+    }
 }
 
 impl WebSocketSupervisor for WebSocketSupervisorReal {
@@ -83,6 +113,8 @@ impl WebSocketSupervisor for WebSocketSupervisorReal {
 impl WebSocketSupervisorReal {
     pub fn new(
         port: u16,
+        tls_opt: Option<UiTlsConfig>,
+        auth_opt: Option<UiAuthConfig>,
         from_ui_message_sub: Recipient<NodeFromUiMessage>,
     ) -> std::io::Result<WebSocketSupervisorReal> {
         let inner = Arc::new(Mutex::new(WebSocketSupervisorInner {
@@ -91,18 +123,89 @@ impl WebSocketSupervisorReal {
             from_ui_message_sub,
             client_id_by_socket_addr: HashMap::new(),
             client_by_id: HashMap::new(),
+            authenticator: UiAuthenticator::new(auth_opt),
+            permission_by_client_id: HashMap::new(),
+            subscriptions: UiSubscriptions::new(),
         }));
         let logger = Logger::new("WebSocketSupervisor");
+        match tls_opt {
+            None => Self::serve_cleartext(port, inner.clone(), logger),
+            Some(tls_config) => {
+                let acceptor = tls::make_acceptor(&tls_config, &logger)?;
+                for ip_addr in Self::listen_addresses(&tls_config.interfaces) {
+                    Self::serve_tls(
+                        SocketAddr::new(ip_addr, port),
+                        acceptor.clone(),
+                        tls_config.allowlist.clone(),
+                        inner.clone(),
+                        logger.clone(),
+                    )
+                }
+            }
+        }
+        Ok(WebSocketSupervisorReal { inner })
+    }
+
+    fn serve_cleartext(port: u16, inner: Arc<Mutex<WebSocketSupervisorInner>>, logger: Logger) {
         let logger_1 = logger.clone();
         let server_address = SocketAddr::new(localhost(), port);
         let server = Server::bind(server_address, &Handle::default())
             .unwrap_or_else(|e| panic!("Could not start UI server at {}: {}", server_address, e));
         let upgrade_tuple_stream = Self::remove_failures(server.incoming(), &logger);
-        let inner_clone = inner.clone();
         let foreach_result = upgrade_tuple_stream.for_each(move |(upgrade, socket_addr)| {
-            Self::handle_upgrade_request(upgrade, socket_addr, inner_clone.clone(), &logger);
+            Self::handle_upgrade_request(upgrade, socket_addr, inner.clone(), &logger);
             Ok(())
         });
+        Self::spawn_server(foreach_result, logger_1)
+    }
+
+    // With TLS on, the UI port is open to the world, or to as much of it as the allowlist lets
+    // in, but anybody who isn't on this machine has to talk to it through TLS. Whether a client
+    // does is decided by the first byte it sends.
+    fn serve_tls(
+        server_address: SocketAddr,
+        acceptor: SslAcceptor,
+        allowlist: Vec<IpNetwork>,
+        inner: Arc<Mutex<WebSocketSupervisorInner>>,
+        logger: Logger,
+    ) {
+        let logger_1 = logger.clone();
+        let listener = TcpListener::bind(&server_address)
+            .unwrap_or_else(|e| panic!("Could not start UI server at {}: {}", server_address, e));
+        let stream_stream = Self::remove_failures(listener.incoming(), &logger);
+        let foreach_result = stream_stream.for_each(move |stream| {
+            Self::handle_tls_mode_connection(
+                stream,
+                acceptor.clone(),
+                &allowlist,
+                inner.clone(),
+                &logger,
+            );
+            Ok(())
+        });
+        Self::spawn_server(foreach_result, logger_1)
+    }
+
+    // Every interface if none are named; otherwise the ones named, plus localhost so that UIs on
+    // this machine can always get in
+    fn listen_addresses(interfaces: &[IpAddr]) -> Vec<IpAddr> {
+        if interfaces.is_empty() {
+            return vec![IpAddr::V4(Ipv4Addr::UNSPECIFIED)];
+        }
+        let mut addresses = interfaces.iter().copied().unique().collect_vec();
+        if !addresses
+            .iter()
+            .any(|address| address.is_loopback() || address.is_unspecified())
+        {
+            addresses.push(localhost());
+        }
+        addresses
+    }
+
+    fn spawn_server<E>(
+        foreach_result: impl Future<Item = (), Error = E> + Send + 'static,
+        logger_1: Logger,
+    ) {
         tokio::spawn(foreach_result.then(move |result| match result {
             Ok(_) => {
                 debug!(logger_1, "WebSocketSupervisor accepted a connection");
@@ -116,19 +219,130 @@ impl WebSocketSupervisorReal {
                 Err(())
             }
         }));
-        Ok(WebSocketSupervisorReal { inner })
+    }
+
+    fn handle_tls_mode_connection(
+        stream: TcpStream,
+        acceptor: SslAcceptor,
+        allowlist: &[IpNetwork],
+        inner: Arc<Mutex<WebSocketSupervisorInner>>,
+        logger: &Logger,
+    ) {
+        let socket_addr = match stream.peer_addr() {
+            Ok(socket_addr) => socket_addr,
+            Err(e) => {
+                warning!(
+                    logger,
+                    "Unsuccessful connection to UI port detected: {:?}",
+                    e
+                );
+                return;
+            }
+        };
+        if !Self::connection_allowed_from(socket_addr, allowlist) {
+            warning!(
+                logger,
+                "Refusing UI connection from {}: it's not on the allowlist",
+                socket_addr
+            );
+            return;
+        }
+        let logger = logger.clone();
+        let connection_future = TlsSniffer::new(stream).then(
+            move |result| -> Box<dyn Future<Item = (), Error = ()> + Send> {
+                match result {
+                    Ok((stream, true)) => {
+                        let logger_1 = logger.clone();
+                        Box::new(
+                            TlsHandshake::new(acceptor, stream)
+                                .map_err(move |e| {
+                                    warning!(
+                                        logger_1,
+                                        "TLS handshake with UI at {} failed: {}",
+                                        socket_addr,
+                                        e
+                                    )
+                                })
+                                .and_then(move |tls_stream| {
+                                    Self::upgrade_stream(tls_stream, socket_addr, inner, logger)
+                                }),
+                        )
+                    }
+                    Ok((stream, false)) if Self::cleartext_allowed_from(socket_addr) => {
+                        Box::new(Self::upgrade_stream(stream, socket_addr, inner, logger))
+                    }
+                    Ok((_, false)) => {
+                        warning!(
+                            logger,
+                            "Refusing unencrypted UI connection from {}: only TLS is accepted from other machines",
+                            socket_addr
+                        );
+                        Box::new(ok::<(), ()>(()))
+                    }
+                    Err(e) => {
+                        warning!(logger, "Unsuccessful connection to UI port detected: {:?}", e);
+                        Box::new(ok::<(), ()>(()))
+                    }
+                }
+            },
+        );
+        tokio::spawn(connection_future);
+    }
+
+    fn cleartext_allowed_from(socket_addr: SocketAddr) -> bool {
+        socket_addr.ip().is_loopback()
+    }
+
+    fn connection_allowed_from(socket_addr: SocketAddr, allowlist: &[IpNetwork]) -> bool {
+        socket_addr.ip().is_loopback()
+            || allowlist.is_empty()
+            || allowlist
+                .iter()
+                .any(|network| network.contains(socket_addr.ip()))
+    }
+
+    fn upgrade_stream<S: WsStream + Send + 'static>(
+        stream: S,
+        socket_addr: SocketAddr,
+        inner: Arc<Mutex<WebSocketSupervisorInner>>,
+        logger: Logger,
+    ) -> impl Future<Item = (), Error = ()> + Send {
+        let logger_1 = logger.clone();
+        stream
+            .into_ws()
+            .map(move |upgrade| Self::handle_upgrade_request(upgrade, socket_addr, inner, &logger))
+            .map_err(move |(_, _, _, e)| {
+                warning!(
+                    logger_1,
+                    "Unsuccessful connection to UI port detected: {:?}",
+                    e
+                )
+            })
     }
 
     fn send_msg(locked_inner: &mut MutexGuard<WebSocketSupervisorInner>, msg: NodeToUiMessage) {
+        // Broadcasts are for clients that have shown they're allowed to see them, and event
+        // broadcasts are only for clients that have subscribed to them
+        let subscribable = subscriptions::is_subscribable(&msg.body.opcode);
+        let wants = |client_id: &u64| {
+            locked_inner.permission_of(*client_id).is_some()
+                && (!subscribable || locked_inner.subscriptions.wants(*client_id, &msg.body))
+        };
         let client_ids = match msg.target {
             MessageTarget::ClientId(n) => vec![n],
             MessageTarget::AllExcept(n) => locked_inner
                 .client_by_id
                 .keys()
                 .filter(|k| k != &&n)
+                .filter(|k| wants(k))
+                .copied()
+                .collect_vec(),
+            MessageTarget::AllClients => locked_inner
+                .client_by_id
+                .keys()
+                .filter(|k| wants(k))
                 .copied()
                 .collect_vec(),
-            MessageTarget::AllClients => locked_inner.client_by_id.keys().copied().collect_vec(),
         };
         let json = UiTrafficConverter::new_marshal(msg.body);
         Self::send_to_clients(locked_inner, client_ids, json);
@@ -155,8 +369,8 @@ impl WebSocketSupervisorReal {
             .map(|option| option.expect("A None magically got through the filter"))
     }
 
-    fn handle_upgrade_request(
-        upgrade: WsUpgrade<TcpStream, BytesMut>,
+    fn handle_upgrade_request<S: WsStream + Send + 'static>(
+        upgrade: WsUpgrade<S, BytesMut>,
         socket_addr: SocketAddr,
         inner: Arc<Mutex<WebSocketSupervisorInner>>,
         logger: &Logger,
@@ -171,8 +385,8 @@ impl WebSocketSupervisorReal {
         }
     }
 
-    fn accept_upgrade_request(
-        upgrade: WsUpgrade<TcpStream, BytesMut>,
+    fn accept_upgrade_request<S: WsStream + Send + 'static>(
+        upgrade: WsUpgrade<S, BytesMut>,
         socket_addr: SocketAddr,
         inner: Arc<Mutex<WebSocketSupervisorInner>>,
         logger: &Logger,
@@ -194,7 +408,10 @@ impl WebSocketSupervisorReal {
         }));
     }
 
-    fn reject_upgrade_request(upgrade: WsUpgrade<TcpStream, BytesMut>, logger: &Logger) {
+    fn reject_upgrade_request<S: WsStream + Send + 'static>(
+        upgrade: WsUpgrade<S, BytesMut>,
+        logger: &Logger,
+    ) {
         info!(
             logger,
             "UI attempted connection without protocol {}: {:?}",
@@ -204,8 +421,8 @@ impl WebSocketSupervisorReal {
         tokio::spawn(upgrade.reject().then(|_| ok::<(), ()>(())));
     }
 
-    fn handle_connection(
-        client: Framed<TcpStream, MessageCodec<OwnedMessage>>,
+    fn handle_connection<S: WsStream + Send + 'static>(
+        client: Framed<S, MessageCodec<OwnedMessage>>,
         inner: &Arc<Mutex<WebSocketSupervisorInner>>,
         logger: &Logger,
         socket_addr: SocketAddr,
@@ -265,10 +482,43 @@ impl WebSocketSupervisorReal {
         };
         match UiTrafficConverter::new_unmarshal_from_ui(message, client_id) {
             Ok(from_ui_message) => {
-                locked_inner
-                    .from_ui_message_sub
-                    .try_send(from_ui_message)
-                    .expect("UiGateway is dead");
+                if from_ui_message.body.opcode == UiAuthenticateRequest::type_opcode() {
+                    Self::handle_authentication(
+                        &mut locked_inner,
+                        client_id,
+                        socket_addr,
+                        from_ui_message.body,
+                        logger,
+                    );
+                    return ok::<(), ()>(());
+                }
+                match locked_inner.permission_of(client_id) {
+                    Some(permission) if permission.allows(&from_ui_message.body.opcode) => {
+                        let opcode = from_ui_message.body.opcode.as_str();
+                        if opcode == UiSubscribeRequest::type_opcode()
+                            || opcode == UiUnsubscribeRequest::type_opcode()
+                        {
+                            Self::handle_subscription(
+                                &mut locked_inner,
+                                client_id,
+                                from_ui_message.body,
+                            );
+                        } else {
+                            locked_inner
+                                .from_ui_message_sub
+                                .try_send(from_ui_message)
+                                .expect("UiGateway is dead");
+                        }
+                    }
+                    permission_opt => Self::refuse_message(
+                        &mut locked_inner,
+                        client_id,
+                        socket_addr,
+                        from_ui_message.body,
+                        permission_opt,
+                        logger,
+                    ),
+                }
             }
             Err(Critical(e)) => {
                 error!(
@@ -331,6 +581,168 @@ impl WebSocketSupervisorReal {
         ok::<(), ()>(())
     }
 
+    fn handle_authentication(
+        locked_inner: &mut MutexGuard<WebSocketSupervisorInner>,
+        client_id: u64,
+        socket_addr: SocketAddr,
+        body: MessageBody,
+        logger: &Logger,
+    ) {
+        let (request, context_id) = match UiAuthenticateRequest::fmb(body.clone()) {
+            Ok(pair) => pair,
+            Err(e) => {
+                error!(
+                    logger,
+                    "Bad authentication request from client {} at {}: {}",
+                    client_id,
+                    socket_addr,
+                    e
+                );
+                let payload = Err((UNMARSHAL_ERROR, e.to_string()));
+                Self::reply(locked_inner, client_id, body, payload);
+                return;
+            }
+        };
+        let result = locked_inner.authenticator.authenticate(
+            socket_addr.ip(),
+            &request.password,
+            Instant::now(),
+        );
+        let response = match result {
+            Ok(permission) => {
+                info!(
+                    logger,
+                    "UI at {} (client ID {}) authenticated for {} access",
+                    socket_addr,
+                    client_id,
+                    permission.name()
+                );
+                locked_inner
+                    .permission_by_client_id
+                    .insert(client_id, permission);
+                UiAuthenticateResponse {
+                    permission: permission.name().to_string(),
+                }
+                .tmb(context_id)
+            }
+            Err(failure) => {
+                let message = match failure {
+                    AuthenticationFailure::WrongPassword { attempts_left } => format!(
+                        "Wrong password; {} more tries before {} is locked out",
+                        attempts_left,
+                        socket_addr.ip()
+                    ),
+                    AuthenticationFailure::LockedOut { seconds_left } => format!(
+                        "Too many wrong passwords from {}; try again in {} seconds",
+                        socket_addr.ip(),
+                        seconds_left
+                    ),
+                };
+                warning!(
+                    logger,
+                    "UI at {} (client ID {}) failed to authenticate: {}",
+                    socket_addr,
+                    client_id,
+                    message
+                );
+                MessageBody {
+                    opcode: UiAuthenticateRequest::type_opcode().to_string(),
+                    path: Conversation(context_id),
+                    payload: Err((AUTHENTICATION_ERROR, message)),
+                }
+            }
+        };
+        Self::send_msg(
+            locked_inner,
+            NodeToUiMessage {
+                target: ClientId(client_id),
+                body: response,
+            },
+        );
+    }
+
+    fn handle_subscription(
+        locked_inner: &mut MutexGuard<WebSocketSupervisorInner>,
+        client_id: u64,
+        body: MessageBody,
+    ) {
+        let payload = if body.opcode == UiSubscribeRequest::type_opcode() {
+            match UiSubscribeRequest::fmb(body.clone()) {
+                Ok((request, _)) => locked_inner
+                    .subscriptions
+                    .subscribe(client_id, &request)
+                    .map(|opcodes| UiSubscribeResponse { opcodes }.tmb(0).payload)
+                    .unwrap_or_else(|e| Err((SUBSCRIPTION_ERROR, e))),
+                Err(e) => Err((UNMARSHAL_ERROR, e.to_string())),
+            }
+        } else {
+            match UiUnsubscribeRequest::fmb(body.clone()) {
+                Ok((request, _)) => {
+                    let opcodes = locked_inner
+                        .subscriptions
+                        .unsubscribe(client_id, &request.opcodes);
+                    UiUnsubscribeResponse { opcodes }.tmb(0).payload
+                }
+                Err(e) => Err((UNMARSHAL_ERROR, e.to_string())),
+            }
+        };
+        set_ui_log_level_limit(locked_inner.subscriptions.log_level_limit());
+        Self::reply(locked_inner, client_id, body, payload);
+    }
+
+    fn refuse_message(
+        locked_inner: &mut MutexGuard<WebSocketSupervisorInner>,
+        client_id: u64,
+        socket_addr: SocketAddr,
+        body: MessageBody,
+        permission_opt: Option<UiPermission>,
+        logger: &Logger,
+    ) {
+        let message = match permission_opt {
+            None => format!("Authenticate before sending '{}'", body.opcode),
+            Some(permission) => format!(
+                "A {} connection may not send '{}'",
+                permission.name(),
+                body.opcode
+            ),
+        };
+        warning!(
+            logger,
+            "Refused message from client {} at {}: {}",
+            client_id,
+            socket_addr,
+            message
+        );
+        Self::reply(
+            locked_inner,
+            client_id,
+            body,
+            Err((PERMISSION_ERROR, message)),
+        );
+    }
+
+    // One-way messages can't be answered; they're just dropped
+    fn reply(
+        locked_inner: &mut MutexGuard<WebSocketSupervisorInner>,
+        client_id: u64,
+        body: MessageBody,
+        payload: Result<String, (u64, String)>,
+    ) {
+        if let Conversation(context_id) = body.path {
+            Self::send_msg(
+                locked_inner,
+                NodeToUiMessage {
+                    target: ClientId(client_id),
+                    body: MessageBody {
+                        opcode: body.opcode,
+                        path: Conversation(context_id),
+                        payload,
+                    },
+                },
+            );
+        }
+    }
+
     fn handle_close_message(
         inner_arc: &Arc<Mutex<WebSocketSupervisorInner>>,
         logger: &Logger,
@@ -427,6 +839,9 @@ impl WebSocketSupervisorReal {
             Some(client) => client,
             None => panic!("WebSocketSupervisor got a disconnect from a client that has disappeared from the stable!"),
         };
+        locked_inner.permission_by_client_id.remove(&client_id);
+        locked_inner.subscriptions.forget(client_id);
+        set_ui_log_level_limit(locked_inner.subscriptions.log_level_limit());
         match client.send(OwnedMessage::Close(None)) {
             Err(e) => warning!(
                 logger,
@@ -459,11 +874,13 @@ mod tests {
         UNMARSHAL_ERROR,
     };
     use masq_lib::test_utils::ui_connection::UiConnection;
+    use masq_lib::test_utils::utils::ensure_node_home_directory_exists;
     use masq_lib::ui_gateway::MessagePath::FireAndForget;
     use masq_lib::ui_gateway::NodeFromUiMessage;
     use masq_lib::ui_traffic_converter::UiTrafficConverter;
     use masq_lib::utils::{find_free_port, localhost};
     use std::cell::RefCell;
+    use std::io::{Read, Write};
     use std::net::Shutdown;
     use std::str::FromStr;
     use std::thread;
@@ -627,7 +1044,8 @@ mod tests {
             let system = System::new("logs_pre_upgrade_connection_errors");
             let ui_message_sub = subs(ui_gateway);
             let subject = lazy(move || {
-                let _subject = WebSocketSupervisorReal::new(port, ui_message_sub).unwrap();
+                let _subject =
+                    WebSocketSupervisorReal::new(port, None, None, ui_message_sub).unwrap();
                 Ok(())
             });
             actix::spawn(subject);
@@ -649,7 +1067,8 @@ mod tests {
             let system = System::new("rejects_connection_attempt_with_improper_protocol_name");
             let ui_message_sub = subs(ui_gateway);
             let subject = lazy(move || {
-                let _subject = WebSocketSupervisorReal::new(port, ui_message_sub).unwrap();
+                let _subject =
+                    WebSocketSupervisorReal::new(port, None, None, ui_message_sub).unwrap();
                 Ok(())
             });
             actix::spawn(subject);
@@ -666,6 +1085,219 @@ mod tests {
         );
     }
 
+    #[test]
+    fn accepts_tls_from_anywhere_and_cleartext_from_localhost_when_tls_is_on() {
+        let home_dir = ensure_node_home_directory_exists(
+            "websocket_supervisor",
+            "accepts_tls_from_anywhere_and_cleartext_from_localhost_when_tls_is_on",
+        );
+        let tls_config = UiTlsConfig {
+            certificate_path: home_dir.join("ui-tls-certificate.pem"),
+            key_path: home_dir.join("ui-tls-key.pem"),
+            generate: true,
+            interfaces: vec![],
+            allowlist: vec![],
+            client_ca_path_opt: None,
+        };
+        let port = find_free_port();
+        let (ui_gateway, _, _) = make_recorder();
+
+        thread::spawn(move || {
+            let system = System::new(
+                "accepts_tls_from_anywhere_and_cleartext_from_localhost_when_tls_is_on",
+            );
+            let ui_message_sub = subs(ui_gateway);
+            let subject = lazy(move || {
+                let _subject =
+                    WebSocketSupervisorReal::new(port, Some(tls_config), None, ui_message_sub)
+                        .unwrap();
+                Ok(())
+            });
+            actix::spawn(subject);
+            system.run();
+        });
+        wait_for_server(port);
+
+        let connector = native_tls::TlsConnector::builder()
+            .danger_accept_invalid_certs(true)
+            .build()
+            .unwrap();
+        let tcp_stream = TcpStream::connect(SocketAddr::new(localhost(), port)).unwrap();
+        let mut tls_stream = connector.connect("localhost", tcp_stream).unwrap();
+        tls_stream
+            .write_all(
+                format!(
+                    "GET / HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\n\
+                     Connection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+                     Sec-WebSocket-Version: 13\r\nSec-WebSocket-Protocol: {}\r\n\r\n",
+                    NODE_UI_PROTOCOL
+                )
+                .as_bytes(),
+            )
+            .unwrap();
+        let mut response = vec![0u8; 1024];
+        let len = tls_stream.read(&mut response).unwrap();
+        let cleartext_client = UiConnection::make(port, NODE_UI_PROTOCOL);
+
+        let response = String::from_utf8_lossy(&response[..len]).to_string();
+        assert_eq!(
+            response.starts_with("HTTP/1.1 101"),
+            true,
+            "Response was: {}",
+            response
+        );
+        assert_eq!(
+            response.contains(&format!("Sec-WebSocket-Protocol: {}", NODE_UI_PROTOCOL)),
+            true
+        );
+        assert_eq!(cleartext_client.is_ok(), true);
+    }
+
+    #[test]
+    fn cleartext_is_allowed_only_from_this_machine() {
+        assert_eq!(
+            WebSocketSupervisorReal::cleartext_allowed_from(
+                SocketAddr::from_str("127.0.0.1:1234").unwrap()
+            ),
+            true
+        );
+        assert_eq!(
+            WebSocketSupervisorReal::cleartext_allowed_from(
+                SocketAddr::from_str("[::1]:1234").unwrap()
+            ),
+            true
+        );
+        assert_eq!(
+            WebSocketSupervisorReal::cleartext_allowed_from(
+                SocketAddr::from_str("192.168.0.10:1234").unwrap()
+            ),
+            false
+        );
+    }
+
+    #[test]
+    fn refuses_tls_clients_without_a_certificate_when_a_client_ca_is_required() {
+        let home_dir = ensure_node_home_directory_exists(
+            "websocket_supervisor",
+            "refuses_tls_clients_without_a_certificate_when_a_client_ca_is_required",
+        );
+        let client_ca_path = home_dir.join("client-ca.pem");
+        let ca_config = UiTlsConfig {
+            certificate_path: client_ca_path.clone(),
+            key_path: home_dir.join("client-ca-key.pem"),
+            generate: true,
+            interfaces: vec![],
+            allowlist: vec![],
+            client_ca_path_opt: None,
+        };
+        tls::make_acceptor(&ca_config, &Logger::new("test")).unwrap();
+        let tls_config = UiTlsConfig {
+            certificate_path: home_dir.join("ui-tls-certificate.pem"),
+            key_path: home_dir.join("ui-tls-key.pem"),
+            generate: true,
+            interfaces: vec![],
+            allowlist: vec![],
+            client_ca_path_opt: Some(client_ca_path),
+        };
+        let port = find_free_port();
+        let (ui_gateway, _, _) = make_recorder();
+
+        thread::spawn(move || {
+            let system = System::new(
+                "refuses_tls_clients_without_a_certificate_when_a_client_ca_is_required",
+            );
+            let ui_message_sub = subs(ui_gateway);
+            let subject = lazy(move || {
+                let _subject =
+                    WebSocketSupervisorReal::new(port, Some(tls_config), None, ui_message_sub)
+                        .unwrap();
+                Ok(())
+            });
+            actix::spawn(subject);
+            system.run();
+        });
+        wait_for_server(port);
+
+        let connector = native_tls::TlsConnector::builder()
+            .danger_accept_invalid_certs(true)
+            .build()
+            .unwrap();
+        let tcp_stream = TcpStream::connect(SocketAddr::new(localhost(), port)).unwrap();
+        // Under TLS 1.3 the client may finish its half of the handshake before the server objects
+        let upgraded = match connector.connect("localhost", tcp_stream) {
+            Err(_) => false,
+            Ok(mut tls_stream) => {
+                let _ = tls_stream.write_all(
+                    format!(
+                        "GET / HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\n\
+                         Connection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+                         Sec-WebSocket-Version: 13\r\nSec-WebSocket-Protocol: {}\r\n\r\n",
+                        NODE_UI_PROTOCOL
+                    )
+                    .as_bytes(),
+                );
+                let mut response = vec![0u8; 1024];
+                match tls_stream.read(&mut response) {
+                    Ok(len) => response[..len].starts_with(b"HTTP/1.1 101"),
+                    Err(_) => false,
+                }
+            }
+        };
+
+        assert_eq!(upgraded, false);
+    }
+
+    #[test]
+    fn connections_are_allowed_only_from_localhost_and_the_allowlist() {
+        let allowlist = vec![
+            IpNetwork::from_str("192.168.0.0/24").unwrap(),
+            IpNetwork::from_str("203.0.113.7").unwrap(),
+        ];
+        let allowed = |address: &str| {
+            WebSocketSupervisorReal::connection_allowed_from(
+                SocketAddr::from_str(address).unwrap(),
+                &allowlist,
+            )
+        };
+
+        assert_eq!(allowed("127.0.0.1:1234"), true);
+        assert_eq!(allowed("192.168.0.10:1234"), true);
+        assert_eq!(allowed("203.0.113.7:1234"), true);
+        assert_eq!(allowed("203.0.113.8:1234"), false);
+        assert_eq!(
+            WebSocketSupervisorReal::connection_allowed_from(
+                SocketAddr::from_str("203.0.113.8:1234").unwrap(),
+                &[]
+            ),
+            true
+        );
+    }
+
+    #[test]
+    fn listens_on_every_interface_or_on_the_named_ones_and_localhost() {
+        let named = vec![
+            IpAddr::from_str("10.8.0.1").unwrap(),
+            IpAddr::from_str("10.8.0.1").unwrap(),
+        ];
+        let with_loopback = vec![
+            IpAddr::from_str("10.8.0.1").unwrap(),
+            IpAddr::from_str("127.0.0.1").unwrap(),
+        ];
+
+        assert_eq!(
+            WebSocketSupervisorReal::listen_addresses(&[]),
+            vec![IpAddr::V4(Ipv4Addr::UNSPECIFIED)]
+        );
+        assert_eq!(
+            WebSocketSupervisorReal::listen_addresses(&named),
+            vec![IpAddr::from_str("10.8.0.1").unwrap(), localhost()]
+        );
+        assert_eq!(
+            WebSocketSupervisorReal::listen_addresses(&with_loopback),
+            with_loopback
+        );
+    }
+
     #[test]
     fn logs_unexpected_binary_ping_pong_websocket_messages() {
         init_test_logging();
@@ -676,7 +1308,8 @@ mod tests {
             let system = System::new("logs_unexpected_binary_ping_pong_websocket_messages");
             let ui_message_sub = subs(ui_gateway);
             let subject = lazy(move || {
-                let _subject = WebSocketSupervisorReal::new(port, ui_message_sub).unwrap();
+                let _subject =
+                    WebSocketSupervisorReal::new(port, None, None, ui_message_sub).unwrap();
                 Ok(())
             });
             actix::spawn(subject);
@@ -713,7 +1346,8 @@ mod tests {
             let system = System::new("can_connect_two_clients_and_receive_messages_from_them");
             let ui_message_sub = subs(ui_gateway);
             let subject = lazy(move || {
-                let _subject = WebSocketSupervisorReal::new(port, ui_message_sub).unwrap();
+                let _subject =
+                    WebSocketSupervisorReal::new(port, None, None, ui_message_sub).unwrap();
                 Ok(())
             });
             actix::spawn(subject);
@@ -797,6 +1431,9 @@ mod tests {
             from_ui_message_sub: ui_message_sub.start().recipient::<NodeFromUiMessage>(),
             client_id_by_socket_addr: Default::default(),
             client_by_id: Default::default(),
+            authenticator: UiAuthenticator::new(None),
+            permission_by_client_id: Default::default(),
+            subscriptions: UiSubscriptions::new(),
         };
         let subject = WebSocketSupervisorReal {
             inner: Arc::new(Mutex::new(subject_inner)),
@@ -864,6 +1501,9 @@ mod tests {
             from_ui_message_sub: ui_message_sub.start().recipient::<NodeFromUiMessage>(),
             client_id_by_socket_addr: Default::default(),
             client_by_id: Default::default(),
+            authenticator: UiAuthenticator::new(None),
+            permission_by_client_id: Default::default(),
+            subscriptions: UiSubscriptions::new(),
         };
         let subject = WebSocketSupervisorReal {
             inner: Arc::new(Mutex::new(subject_inner)),
@@ -931,6 +1571,9 @@ mod tests {
             from_ui_message_sub: ui_message_sub.start().recipient::<NodeFromUiMessage>(),
             client_id_by_socket_addr: Default::default(),
             client_by_id: Default::default(),
+            authenticator: UiAuthenticator::new(None),
+            permission_by_client_id: Default::default(),
+            subscriptions: UiSubscriptions::new(),
         };
         let subject = WebSocketSupervisorReal {
             inner: Arc::new(Mutex::new(subject_inner)),
@@ -1007,6 +1650,9 @@ mod tests {
             from_ui_message_sub,
             client_id_by_socket_addr: Default::default(),
             client_by_id,
+            authenticator: UiAuthenticator::new(None),
+            permission_by_client_id: Default::default(),
+            subscriptions: UiSubscriptions::new(),
         }));
 
         WebSocketSupervisorReal::send_to_clients(
@@ -1027,7 +1673,8 @@ mod tests {
             let system = System::new("once_a_client_sends_a_close_no_more_data_is_accepted");
             let ui_message_sub = subs(ui_gateway);
             let subject = lazy(move || {
-                let _subject = WebSocketSupervisorReal::new(port, ui_message_sub).unwrap();
+                let _subject =
+                    WebSocketSupervisorReal::new(port, None, None, ui_message_sub).unwrap();
                 Ok(())
             });
             actix::spawn(subject);
@@ -1038,7 +1685,7 @@ mod tests {
 
         client.send(UiShutdownRequest {});
         client.send_message(&OwnedMessage::Close(None));
-        client.send(UiStartOrder {});
+        client.send(UiStartOrder { instance_opt: None });
 
         client.shutdown();
         ui_gateway_awaiter.await_message_count(1);
@@ -1063,7 +1710,8 @@ mod tests {
             let system = System::new("a_client_that_violates_the_protocol_is_terminated");
             let ui_message_sub = subs(ui_gateway);
             let subject = lazy(move || {
-                let _subject = WebSocketSupervisorReal::new(port, ui_message_sub).unwrap();
+                let _subject =
+                    WebSocketSupervisorReal::new(port, None, None, ui_message_sub).unwrap();
                 Ok(())
             });
             actix::spawn(subject);
@@ -1075,7 +1723,7 @@ mod tests {
             let writer = client.writer();
             writer.write(b"Booga!").unwrap();
         }
-        client.send(UiStartOrder {});
+        client.send(UiStartOrder { instance_opt: None });
         ui_gateway_awaiter.await_message_count(1);
         thread::sleep(Duration::from_millis(500)); // make sure there's not another message sent
         let ui_gateway_recording = ui_gateway_recording_arc.lock().unwrap();
@@ -1096,7 +1744,7 @@ mod tests {
         let ui_message_sub = subs(ui_gateway);
         let system = System::new("send_msg_sends_a_message_to_the_client");
         let lazy_future = lazy(move || {
-            let subject = WebSocketSupervisorReal::new(port, ui_message_sub).unwrap();
+            let subject = WebSocketSupervisorReal::new(port, None, None, ui_message_sub).unwrap();
             let one_mock_client = ClientWrapperMock::new()
                 .send_result(Ok(()))
                 .flush_result(Ok(()));
@@ -1137,7 +1785,7 @@ mod tests {
         let ui_message_sub = subs(ui_gateway);
         let system = System::new("send_msg_sends_a_message_to_the_client");
         let lazy_future = lazy(move || {
-            let subject = WebSocketSupervisorReal::new(port, ui_message_sub).unwrap();
+            let subject = WebSocketSupervisorReal::new(port, None, None, ui_message_sub).unwrap();
             let one_mock_client = ClientWrapperMock::new()
                 .send_result(Ok(()))
                 .flush_result(Ok(()));
@@ -1180,7 +1828,7 @@ mod tests {
         let ui_message_sub = subs(ui_gateway);
         let system = System::new("send_msg_sends_a_message_to_the_client");
         let lazy_future = lazy(move || {
-            let subject = WebSocketSupervisorReal::new(port, ui_message_sub).unwrap();
+            let subject = WebSocketSupervisorReal::new(port, None, None, ui_message_sub).unwrap();
             let one_mock_client = ClientWrapperMock::new()
                 .send_result(Ok(()))
                 .flush_result(Ok(()));
@@ -1230,7 +1878,7 @@ mod tests {
         let ui_message_sub = subs(ui_gateway);
         let system = System::new("send_msg_tries_to_send_message_and_panics_on_flush");
         let lazy_future = lazy(move || {
-            let subject = WebSocketSupervisorReal::new(port, ui_message_sub).unwrap();
+            let subject = WebSocketSupervisorReal::new(port, None, None, ui_message_sub).unwrap();
             let mock_client = ClientWrapperMock::new()
                 .send_result(Ok(()))
                 .flush_result(Err(WebSocketError::NoDataAvailable));
@@ -1262,7 +1910,7 @@ mod tests {
         let ui_message_sub = subs(ui_gateway);
         let system = System::new("send_msg_tries_to_send_message_and_panics");
         let lazy_future = lazy(move || {
-            let subject = WebSocketSupervisorReal::new(port, ui_message_sub).unwrap();
+            let subject = WebSocketSupervisorReal::new(port, None, None, ui_message_sub).unwrap();
             let mock_client =
                 ClientWrapperMock::new().send_result(Err(WebSocketError::NoDataAvailable));
             let msg = NodeToUiMessage {
@@ -1292,7 +1940,7 @@ mod tests {
         let ui_message_sub = subs(ui_gateway);
         let system = System::new("send_msg_fails_to_look_up_client_to_send_to");
         let lazy_future = lazy(move || {
-            let subject = WebSocketSupervisorReal::new(port, ui_message_sub).unwrap();
+            let subject = WebSocketSupervisorReal::new(port, None, None, ui_message_sub).unwrap();
             let msg = NodeToUiMessage {
                 target: MessageTarget::ClientId(7),
                 body: MessageBody {
@@ -0,0 +1,419 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use crate::proxy_client::secure_resolver::https_response_body;
+use crate::sub_lib::logger::Logger;
+use actix::Recipient;
+use masq_lib::messages::{ToMessageBody, UiUpdateAvailableBroadcast};
+use masq_lib::ui_gateway::{MessageTarget, NodeToUiMessage};
+use native_tls::{TlsConnector, TlsStream};
+use rustc_hex::FromHex;
+use serde_derive::Deserialize;
+use sodiumoxide::crypto::sign as signing;
+use std::io;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::thread;
+use std::time::Duration;
+
+pub const DEFAULT_UPDATE_CHECK_INTERVAL_HOURS: u64 = 24;
+const FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_MANIFEST_RESPONSE_LEN: usize = 65536;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct UpdateCheckConfig {
+    pub manifest_url: String,
+    pub public_key: Vec<u8>,
+    pub interval: Duration,
+}
+
+// What's served at the manifest URL: the manifest JSON, as a string so that the signature covers
+// exactly the bytes that were signed, and the hex Ed25519 signature of those bytes.
+#[derive(Deserialize, Debug)]
+struct SignedManifest {
+    manifest: String,
+    signature: String,
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct ReleaseManifest {
+    pub version: String,
+    #[serde(rename = "releaseNotesUrl")]
+    pub release_notes_url: String,
+    #[serde(default)]
+    pub security: bool,
+}
+
+pub trait ManifestFetcher: Send {
+    fn fetch(&self, url: &str) -> io::Result<Vec<u8>>;
+}
+
+pub struct ManifestFetcherReal {}
+
+impl ManifestFetcher for ManifestFetcherReal {
+    fn fetch(&self, url: &str) -> io::Result<Vec<u8>> {
+        let mut tls_stream = https_get(url, "application/json")?;
+        let mut response = vec![];
+        let mut buf = [0u8; 4096];
+        loop {
+            // As with DNS-over-HTTPS, a server that closes without close_notify is done talking
+            let at_eof = match tls_stream.read(&mut buf) {
+                Ok(0) | Err(_) => true,
+                Ok(len) => {
+                    response.extend_from_slice(&buf[..len]);
+                    false
+                }
+            };
+            match https_response_body(&response, at_eof) {
+                Ok(Some(body)) => return Ok(body),
+                Ok(None) if response.len() < MAX_MANIFEST_RESPONSE_LEN => (),
+                Ok(None) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "Release manifest too long",
+                    ))
+                }
+                Err(msg) => return Err(io::Error::new(io::ErrorKind::InvalidData, msg)),
+            }
+        }
+    }
+}
+
+pub struct UpdateChecker {
+    config: UpdateCheckConfig,
+    current_version: String,
+    fetcher: Box<dyn ManifestFetcher>,
+    logger: Logger,
+}
+
+impl UpdateChecker {
+    pub fn new(config: UpdateCheckConfig) -> Self {
+        Self {
+            config,
+            current_version: env!("CARGO_PKG_VERSION").to_string(),
+            fetcher: Box::new(ManifestFetcherReal {}),
+            logger: Logger::new("UpdateChecker"),
+        }
+    }
+
+    // Ok(None) means the Node is up to date
+    pub fn check(&self) -> Result<Option<ReleaseManifest>, String> {
+        let signed_bytes = self
+            .fetcher
+            .fetch(&self.config.manifest_url)
+            .map_err(|e| format!("Couldn't fetch {}: {}", self.config.manifest_url, e))?;
+        let manifest = verify(&signed_bytes, &self.config.public_key)?;
+        if is_newer(&manifest.version, &self.current_version)? {
+            Ok(Some(manifest))
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub fn check_and_announce(&self, ui_sub: &Recipient<NodeToUiMessage>) {
+        match self.check() {
+            Ok(Some(manifest)) => {
+                warning!(
+                    self.logger,
+                    "MASQ Node {} is available{}; this is {}. Release notes: {}",
+                    manifest.version,
+                    if manifest.security {
+                        ", with security fixes"
+                    } else {
+                        ""
+                    },
+                    self.current_version,
+                    manifest.release_notes_url
+                );
+                ui_sub
+                    .try_send(NodeToUiMessage {
+                        target: MessageTarget::AllClients,
+                        body: UiUpdateAvailableBroadcast {
+                            current_version: self.current_version.clone(),
+                            latest_version: manifest.version,
+                            release_notes_url: manifest.release_notes_url,
+                            security: manifest.security,
+                        }
+                        .tmb(0),
+                    })
+                    .expect("UiGateway is dead");
+            }
+            Ok(None) => debug!(
+                self.logger,
+                "MASQ Node {} is the latest release", self.current_version
+            ),
+            Err(e) => warning!(self.logger, "Couldn't check for updates: {}", e),
+        }
+    }
+}
+
+// Checks right away, then once every interval, for as long as the Node runs
+pub fn start_update_checker(config: UpdateCheckConfig, ui_sub: Recipient<NodeToUiMessage>) {
+    let checker = UpdateChecker::new(config);
+    info!(
+        checker.logger,
+        "Checking {} for updates every {} hours",
+        checker.config.manifest_url,
+        checker.config.interval.as_secs() / 3600
+    );
+    thread::spawn(move || loop {
+        checker.check_and_announce(&ui_sub);
+        thread::sleep(checker.config.interval);
+    });
+}
+
+fn verify(signed_bytes: &[u8], public_key: &[u8]) -> Result<ReleaseManifest, String> {
+    let signed: SignedManifest = serde_json::from_slice(signed_bytes)
+        .map_err(|e| format!("Release manifest is malformed: {}", e))?;
+    let signature_bytes: Vec<u8> = signed
+        .signature
+        .from_hex()
+        .map_err(|_| "Release manifest signature isn't hexadecimal".to_string())?;
+    let signature = signing::Signature::from_slice(&signature_bytes)
+        .ok_or_else(|| "Release manifest signature is the wrong length".to_string())?;
+    let public_key = signing::PublicKey::from_slice(public_key)
+        .ok_or_else(|| "Update check key is the wrong length".to_string())?;
+    if !signing::verify_detached(&signature, signed.manifest.as_bytes(), &public_key) {
+        return Err("Release manifest signature doesn't match the update check key".to_string());
+    }
+    serde_json::from_str(&signed.manifest)
+        .map_err(|e| format!("Signed release manifest is malformed: {}", e))
+}
+
+// Sends a GET for the URL and returns the stream the response will arrive on
+pub fn https_get(url: &str, accept: &str) -> io::Result<TlsStream<TcpStream>> {
+    let (mut tls_stream, host, path) = https_connect(url)?;
+    write!(
+        tls_stream,
+        "GET {} HTTP/1.1\r\nHost: {}\r\nAccept: {}\r\nConnection: close\r\n\r\n",
+        path, host, accept
+    )?;
+    Ok(tls_stream)
+}
+
+// Sends a POST of the body to the URL, asking for an answer of the same type, and returns the
+// stream the response will arrive on
+pub fn https_post(url: &str, content_type: &str, body: &[u8]) -> io::Result<TlsStream<TcpStream>> {
+    let (mut tls_stream, host, path) = https_connect(url)?;
+    write!(
+        tls_stream,
+        "POST {} HTTP/1.1\r\nHost: {}\r\nAccept: {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        path, host, content_type, content_type, body.len()
+    )?;
+    tls_stream.write_all(body)?;
+    Ok(tls_stream)
+}
+
+fn https_connect(url: &str) -> io::Result<(TlsStream<TcpStream>, String, String)> {
+    let (host, port, path) = split_url(url)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("Bad URL: {}", url)))?;
+    let address = (host.as_str(), port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("Could not find an address for {}", host),
+            )
+        })?;
+    let tcp_stream = TcpStream::connect_timeout(&address, FETCH_TIMEOUT)?;
+    tcp_stream.set_read_timeout(Some(FETCH_TIMEOUT))?;
+    tcp_stream.set_write_timeout(Some(FETCH_TIMEOUT))?;
+    let connector =
+        TlsConnector::new().map_err(|e| io::Error::new(io::ErrorKind::Other, format!("{}", e)))?;
+    let tls_stream = connector.connect(&host, tcp_stream).map_err(|e| {
+        io::Error::new(io::ErrorKind::Other, format!("TLS handshake failed: {}", e))
+    })?;
+    Ok((tls_stream, host, path))
+}
+
+fn is_newer(candidate: &str, current: &str) -> Result<bool, String> {
+    let parse = |version: &str| {
+        version
+            .trim_start_matches('v')
+            .split('.')
+            .map(|part| part.parse::<u64>())
+            .collect::<Result<Vec<u64>, _>>()
+            .map_err(|_| format!("'{}' is not a version number", version))
+    };
+    Ok(parse(candidate)? > parse(current)?)
+}
+
+fn split_url(url: &str) -> Option<(String, u16, String)> {
+    if !url.starts_with("https://") {
+        return None;
+    }
+    let rest = &url["https://".len()..];
+    let (authority, path) = match rest.find('/') {
+        Some(idx) => (&rest[..idx], &rest[idx..]),
+        None => (rest, "/"),
+    };
+    let (host, port) = match authority.rfind(':') {
+        Some(idx) => (
+            &authority[..idx],
+            authority[(idx + 1)..].parse::<u16>().ok()?,
+        ),
+        None => (authority, 443),
+    };
+    if host.is_empty() {
+        None
+    } else {
+        Some((host.to_string(), port, path.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::logging::{init_test_logging, TestLogHandler};
+    use crate::test_utils::recorder::make_recorder;
+    use actix::{Actor, System};
+    use masq_lib::messages::FromMessageBody;
+    use rustc_hex::ToHex;
+    use std::sync::{Arc, Mutex};
+
+    struct ManifestFetcherMock {
+        fetch_params: Arc<Mutex<Vec<String>>>,
+        fetch_result: Result<Vec<u8>, String>,
+    }
+
+    impl ManifestFetcher for ManifestFetcherMock {
+        fn fetch(&self, url: &str) -> io::Result<Vec<u8>> {
+            self.fetch_params.lock().unwrap().push(url.to_string());
+            self.fetch_result
+                .clone()
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+        }
+    }
+
+    fn signed_manifest(manifest: &str, secret_key: &signing::SecretKey) -> Vec<u8> {
+        let signature = signing::sign_detached(manifest.as_bytes(), secret_key);
+        let signature_hex: String = signature.0.to_hex();
+        serde_json::to_vec(&serde_json::json!({
+            "manifest": manifest,
+            "signature": signature_hex,
+        }))
+        .unwrap()
+    }
+
+    fn make_subject(
+        public_key: &signing::PublicKey,
+        fetch_result: Result<Vec<u8>, String>,
+    ) -> (UpdateChecker, Arc<Mutex<Vec<String>>>) {
+        let fetch_params_arc = Arc::new(Mutex::new(vec![]));
+        let mut subject = UpdateChecker::new(UpdateCheckConfig {
+            manifest_url: "https://releases.example.com/manifest.json".to_string(),
+            public_key: public_key.0.to_vec(),
+            interval: Duration::from_secs(3600),
+        });
+        subject.current_version = "1.2.3".to_string();
+        subject.fetcher = Box::new(ManifestFetcherMock {
+            fetch_params: fetch_params_arc.clone(),
+            fetch_result,
+        });
+        (subject, fetch_params_arc)
+    }
+
+    #[test]
+    fn newer_release_is_logged_and_broadcast() {
+        init_test_logging();
+        let (public_key, secret_key) = signing::gen_keypair();
+        let manifest = r#"{"version": "1.10.0", "releaseNotesUrl": "https://releases.example.com/1.10.0", "security": true}"#;
+        let (subject, fetch_params_arc) =
+            make_subject(&public_key, Ok(signed_manifest(manifest, &secret_key)));
+        let (ui_gateway, _, ui_gateway_recording_arc) = make_recorder();
+        let system = System::new("newer_release_is_logged_and_broadcast");
+        let ui_sub = ui_gateway.start().recipient::<NodeToUiMessage>();
+
+        subject.check_and_announce(&ui_sub);
+
+        System::current().stop();
+        system.run();
+        assert_eq!(
+            *fetch_params_arc.lock().unwrap(),
+            vec!["https://releases.example.com/manifest.json".to_string()]
+        );
+        let ui_gateway_recording = ui_gateway_recording_arc.lock().unwrap();
+        let message: &NodeToUiMessage = ui_gateway_recording.get_record(0);
+        assert_eq!(message.target, MessageTarget::AllClients);
+        let (broadcast, _) = UiUpdateAvailableBroadcast::fmb(message.body.clone()).unwrap();
+        assert_eq!(
+            broadcast,
+            UiUpdateAvailableBroadcast {
+                current_version: "1.2.3".to_string(),
+                latest_version: "1.10.0".to_string(),
+                release_notes_url: "https://releases.example.com/1.10.0".to_string(),
+                security: true,
+            }
+        );
+        TestLogHandler::new().exists_log_containing(
+            "WARN: UpdateChecker: MASQ Node 1.10.0 is available, with security fixes; this is 1.2.3. Release notes: https://releases.example.com/1.10.0",
+        );
+    }
+
+    #[test]
+    fn same_or_older_release_is_not_news() {
+        let (public_key, secret_key) = signing::gen_keypair();
+        ["1.2.3", "1.2.2", "v0.9.9"].iter().for_each(|version| {
+            let manifest = format!(
+                r#"{{"version": "{}", "releaseNotesUrl": "https://releases.example.com"}}"#,
+                version
+            );
+            let (subject, _) =
+                make_subject(&public_key, Ok(signed_manifest(&manifest, &secret_key)));
+
+            let result = subject.check();
+
+            assert_eq!(result, Ok(None), "{}", version);
+        });
+    }
+
+    #[test]
+    fn manifest_signed_with_another_key_is_ignored() {
+        let (public_key, _) = signing::gen_keypair();
+        let (_, impostor_secret_key) = signing::gen_keypair();
+        let manifest = r#"{"version": "9.9.9", "releaseNotesUrl": "https://evil.example.com"}"#;
+        let (subject, _) = make_subject(
+            &public_key,
+            Ok(signed_manifest(manifest, &impostor_secret_key)),
+        );
+
+        let result = subject.check();
+
+        assert_eq!(
+            result,
+            Err("Release manifest signature doesn't match the update check key".to_string())
+        );
+    }
+
+    #[test]
+    fn fetch_failure_is_reported() {
+        let (public_key, _) = signing::gen_keypair();
+        let (subject, _) = make_subject(&public_key, Err("Connection refused".to_string()));
+
+        let result = subject.check();
+
+        assert_eq!(
+            result,
+            Err(
+                "Couldn't fetch https://releases.example.com/manifest.json: Connection refused"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn split_url_finds_host_port_and_path() {
+        assert_eq!(
+            split_url("https://releases.example.com/node/manifest.json"),
+            Some((
+                "releases.example.com".to_string(),
+                443,
+                "/node/manifest.json".to_string()
+            ))
+        );
+        assert_eq!(
+            split_url("https://releases.example.com:8443"),
+            Some(("releases.example.com".to_string(), 8443, "/".to_string()))
+        );
+        assert_eq!(split_url("http://releases.example.com/"), None);
+    }
+}
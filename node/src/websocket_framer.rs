@@ -0,0 +1,188 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use crate::sub_lib::framer::FramedChunk;
+use crate::sub_lib::framer::Framer;
+
+// FIN set, opcode 2: a whole binary message in a single frame
+pub const WEBSOCKET_BINARY_FRAME: u8 = 0x82;
+
+#[derive(Debug, PartialEq)]
+pub struct WebSocketHeader {
+    pub header_len: usize,
+    pub payload_len: usize,
+    pub masking_key_opt: Option<[u8; 4]>,
+}
+
+// None until enough of the frame has arrived to say how long it is
+pub fn parse_websocket_header(data: &[u8]) -> Option<WebSocketHeader> {
+    if data.len() < 2 {
+        return None;
+    }
+    let masked = (data[1] & 0x80) != 0;
+    let (length_len, payload_len) = match data[1] & 0x7F {
+        126 if data.len() >= 4 => (2, u16::from_be_bytes([data[2], data[3]]) as usize),
+        127 if data.len() >= 10 => {
+            let mut length = [0u8; 8];
+            length.copy_from_slice(&data[2..10]);
+            (8, u64::from_be_bytes(length) as usize)
+        }
+        126 | 127 => return None,
+        short => (0, short as usize),
+    };
+    let header_len = 2 + length_len + if masked { 4 } else { 0 };
+    if data.len() < header_len {
+        return None;
+    }
+    let masking_key_opt = if masked {
+        let mut key = [0u8; 4];
+        key.copy_from_slice(&data[(header_len - 4)..header_len]);
+        Some(key)
+    } else {
+        None
+    };
+    Some(WebSocketHeader {
+        header_len,
+        payload_len,
+        masking_key_opt,
+    })
+}
+
+#[derive(Default)]
+pub struct WebSocketFramer {
+    data_so_far: Vec<u8>,
+}
+
+impl Framer for WebSocketFramer {
+    fn add_data(&mut self, data: &[u8]) {
+        self.data_so_far.extend_from_slice(data);
+    }
+
+    fn take_frame(&mut self) -> Option<FramedChunk> {
+        match self
+            .data_so_far
+            .iter()
+            .position(|byte| *byte == WEBSOCKET_BINARY_FRAME)
+        {
+            Some(start) => {
+                self.data_so_far.drain(..start);
+            }
+            None => {
+                self.data_so_far.clear();
+                return None;
+            }
+        }
+        let header = parse_websocket_header(&self.data_so_far)?;
+        let frame_len = header.header_len.checked_add(header.payload_len)?;
+        if self.data_so_far.len() < frame_len {
+            return None;
+        }
+        let chunk: Vec<u8> = self.data_so_far.drain(..frame_len).collect();
+        Some(FramedChunk {
+            chunk,
+            last_chunk: true,
+        })
+    }
+}
+
+impl WebSocketFramer {
+    pub fn new() -> WebSocketFramer {
+        WebSocketFramer {
+            data_so_far: vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(payload: &[u8]) -> Vec<u8> {
+        let mut result = vec![WEBSOCKET_BINARY_FRAME];
+        match payload.len() {
+            len if len < 126 => result.push(len as u8),
+            len if len <= 0xFFFF => {
+                result.push(126);
+                result.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+            len => {
+                result.push(127);
+                result.extend_from_slice(&(len as u64).to_be_bytes());
+            }
+        }
+        result.extend_from_slice(payload);
+        result
+    }
+
+    #[test]
+    fn frames_of_every_length_encoding_come_out_whole() {
+        let short = frame(&[1; 125]);
+        let medium = frame(&[2; 126]);
+        let long = frame(&[3; 0x10000]);
+        let mut subject = WebSocketFramer::new();
+        subject.add_data(&short);
+        subject.add_data(&medium);
+        subject.add_data(&long);
+
+        let results = (0..4).map(|_| subject.take_frame()).collect::<Vec<_>>();
+
+        assert_eq!(
+            results,
+            vec![
+                Some(FramedChunk {
+                    chunk: short,
+                    last_chunk: true
+                }),
+                Some(FramedChunk {
+                    chunk: medium,
+                    last_chunk: true
+                }),
+                Some(FramedChunk {
+                    chunk: long,
+                    last_chunk: true
+                }),
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn a_frame_that_arrives_in_pieces_comes_out_when_the_last_piece_arrives() {
+        let whole = frame(&[4; 300]);
+        let mut subject = WebSocketFramer::new();
+        subject.add_data(b"garbage");
+        subject.add_data(&whole[..1]);
+        let after_one_byte = subject.take_frame();
+        subject.add_data(&whole[1..3]);
+        let after_partial_length = subject.take_frame();
+        subject.add_data(&whole[3..200]);
+        let after_partial_payload = subject.take_frame();
+        subject.add_data(&whole[200..]);
+
+        let result = subject.take_frame();
+
+        assert_eq!(after_one_byte, None);
+        assert_eq!(after_partial_length, None);
+        assert_eq!(after_partial_payload, None);
+        assert_eq!(
+            result,
+            Some(FramedChunk {
+                chunk: whole,
+                last_chunk: true
+            })
+        );
+    }
+
+    #[test]
+    fn masked_headers_are_parsed_with_their_keys() {
+        let result =
+            parse_websocket_header(&[WEBSOCKET_BINARY_FRAME, 0x80 | 126, 1, 0, 9, 8, 7, 6]);
+
+        assert_eq!(
+            result,
+            Some(WebSocketHeader {
+                header_len: 8,
+                payload_len: 256,
+                masking_key_opt: Some([9, 8, 7, 6]),
+            })
+        );
+    }
+}
@@ -0,0 +1,125 @@
+// Copyright (c) 2019-2020, MASQ (https://masq.ai) and/or its affiliates. All rights reserved.
+use crate::discriminator::UnmaskedChunk;
+use crate::masquerader::MasqueradeError;
+use crate::masquerader::Masquerader;
+use crate::websocket_framer::{parse_websocket_header, WEBSOCKET_BINARY_FRAME};
+
+// Makes clandestine traffic look like binary messages on an established WebSocket. The frames go
+// out unmasked, as a server's would; masked frames, as a browser's would be, are unmasked too.
+#[derive(Default)]
+pub struct WebSocketMasquerader {}
+
+impl Masquerader for WebSocketMasquerader {
+    fn try_unmask(&self, item: &[u8]) -> Result<UnmaskedChunk, MasqueradeError> {
+        if item.first() != Some(&WEBSOCKET_BINARY_FRAME) {
+            return Err(MasqueradeError::NotThisMasquerader);
+        }
+        let header = match parse_websocket_header(item) {
+            Some(header) => header,
+            None => {
+                return Err(MasqueradeError::LowLevelDataError(
+                    "WebSocket frame header is truncated".to_string(),
+                ))
+            }
+        };
+        if item.len() - header.header_len != header.payload_len {
+            return Err(MasqueradeError::MidLevelDataError(format!(
+                "WebSocket frame says its payload is {} bytes, but it's {}",
+                header.payload_len,
+                item.len() - header.header_len
+            )));
+        }
+        let payload = &item[header.header_len..];
+        let chunk = match header.masking_key_opt {
+            Some(key) => payload
+                .iter()
+                .enumerate()
+                .map(|(index, byte)| byte ^ key[index % 4])
+                .collect(),
+            None => payload.to_vec(),
+        };
+        Ok(UnmaskedChunk::new(chunk, true, false))
+    }
+
+    fn mask(&self, data: &[u8]) -> Result<Vec<u8>, MasqueradeError> {
+        let mut result = Vec::with_capacity(data.len() + 10);
+        result.push(WEBSOCKET_BINARY_FRAME);
+        match data.len() {
+            len if len < 126 => result.push(len as u8),
+            len if len <= 0xFFFF => {
+                result.push(126);
+                result.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+            len => {
+                result.push(127);
+                result.extend_from_slice(&(len as u64).to_be_bytes());
+            }
+        }
+        result.extend_from_slice(data);
+        Ok(result)
+    }
+}
+
+impl WebSocketMasquerader {
+    pub fn new() -> WebSocketMasquerader {
+        WebSocketMasquerader {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn masked_data_of_every_size_unmasks_to_itself() {
+        let subject = WebSocketMasquerader::new();
+        vec![0, 125, 126, 0xFFFF, 0x10000]
+            .into_iter()
+            .map(|len| (0..len).map(|n| (n % 251) as u8).collect::<Vec<u8>>())
+            .for_each(|data| {
+                let masked = subject.mask(&data).unwrap();
+
+                let result = subject.try_unmask(&masked).unwrap();
+
+                assert_eq!(result, UnmaskedChunk::new(data, true, false));
+            });
+    }
+
+    #[test]
+    fn frames_masked_by_a_client_are_unmasked() {
+        let subject = WebSocketMasquerader::new();
+        let key = [0x37, 0xfa, 0x21, 0x3d];
+        let mut frame = vec![WEBSOCKET_BINARY_FRAME, 0x80 | 5];
+        frame.extend_from_slice(&key);
+        frame.extend(b"Hello".iter().enumerate().map(|(i, b)| b ^ key[i % 4]));
+
+        let result = subject.try_unmask(&frame);
+
+        assert_eq!(
+            result,
+            Ok(UnmaskedChunk::new(b"Hello".to_vec(), true, false))
+        );
+    }
+
+    #[test]
+    fn other_masquerades_and_malformed_frames_are_refused() {
+        let subject = WebSocketMasquerader::new();
+
+        assert_eq!(
+            subject.try_unmask(b"{\"bodyText\":\"booga\"}"),
+            Err(MasqueradeError::NotThisMasquerader)
+        );
+        assert_eq!(
+            subject.try_unmask(&[WEBSOCKET_BINARY_FRAME, 126, 1]),
+            Err(MasqueradeError::LowLevelDataError(
+                "WebSocket frame header is truncated".to_string()
+            ))
+        );
+        assert_eq!(
+            subject.try_unmask(&[WEBSOCKET_BINARY_FRAME, 3, 1, 2]),
+            Err(MasqueradeError::MidLevelDataError(
+                "WebSocket frame says its payload is 3 bytes, but it's 2".to_string()
+            ))
+        );
+    }
+}
@@ -79,7 +79,9 @@ fn initialization_sequence_integration() {
             context_id,
         )
         .unwrap_err();
-    let start_response: UiStartResponse = initialization_client.transact(UiStartOrder {}).unwrap();
+    let start_response: UiStartResponse = initialization_client
+        .transact(UiStartOrder { instance_opt: None })
+        .unwrap();
     let running_financials_response: UiRedirect = initialization_client
         .transact_with_context_id(financials_request.clone(), context_id)
         .unwrap();